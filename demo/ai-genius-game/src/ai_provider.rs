@@ -0,0 +1,976 @@
+//! Model APIs behind one interface
+//!
+//! An [`AiProvider`] answers a prompt, whole or as a stream of [`Chunk`]s,
+//! and reports the tokens the answer took. [`OpenAiProvider`],
+//! [`AnthropicProvider`] and [`OllamaProvider`] each speak their API's
+//! dialect; [`Resilient`] puts any of them behind a timeout and retries.
+//!
+//! [`ModelDecisionProvider`] is the [`DecisionProvider`] of an agent backed
+//! by a real model. The model is asked for its action as JSON first and its
+//! reasoning after; the answer is streamed and the action played as soon
+//! as its JSON parses. The rest of the answer is read in the background and
+//! the tokens it took are charged once it ends (see
+//! [`DecisionProvider::late_usage`]).
+//!
+//! [`from_env`] picks the API of a model, so `benchmark --live` plays real
+//! models instead of [`MockProvider`](crate::collective::MockProvider)s.
+
+use async_trait::async_trait;
+use futures::stream::{self, BoxStream, StreamExt};
+use serde_json::{json, Value};
+use std::fmt;
+use std::future::Future;
+use std::ops::AddAssign;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::runtime::Handle;
+use tokio::task::JoinHandle;
+
+use crate::collective::{DecisionProvider, ProviderDecision, ProviderFactory};
+use crate::maze::Direction;
+use crate::{GameAction, GameState, GameType};
+
+/// Time an attempt, or a streamed chunk, may take unless told otherwise
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Attempts at a call unless told otherwise
+pub const DEFAULT_ATTEMPTS: u32 = 3;
+
+/// Wait before the first retry, doubled before each one after
+pub const DEFAULT_BACKOFF: Duration = Duration::from_millis(500);
+
+/// Anthropic needs a cap on the length of every answer
+const ANTHROPIC_MAX_TOKENS: u32 = 1024;
+const ANTHROPIC_VERSION: &str = "2023-06-01";
+
+const OPENAI_URL: &str = "https://api.openai.com";
+const ANTHROPIC_URL: &str = "https://api.anthropic.com";
+const OLLAMA_URL: &str = "http://localhost:11434";
+
+/// Tokens a call took
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TokenUsage {
+    pub prompt_tokens: u32,
+    pub completion_tokens: u32,
+}
+
+impl TokenUsage {
+    /// The larger of each count; streams report running totals, some only
+    /// one count at a time
+    pub fn max(self, other: TokenUsage) -> TokenUsage {
+        TokenUsage {
+            prompt_tokens: self.prompt_tokens.max(other.prompt_tokens),
+            completion_tokens: self.completion_tokens.max(other.completion_tokens),
+        }
+    }
+
+    /// What was used beyond `earlier`
+    pub fn since(self, earlier: TokenUsage) -> TokenUsage {
+        TokenUsage {
+            prompt_tokens: self.prompt_tokens.saturating_sub(earlier.prompt_tokens),
+            completion_tokens: self.completion_tokens.saturating_sub(earlier.completion_tokens),
+        }
+    }
+}
+
+impl AddAssign for TokenUsage {
+    fn add_assign(&mut self, other: TokenUsage) {
+        self.prompt_tokens += other.prompt_tokens;
+        self.completion_tokens += other.completion_tokens;
+    }
+}
+
+/// A whole answer
+#[derive(Debug, Clone, PartialEq)]
+pub struct Completion {
+    pub text: String,
+    pub usage: TokenUsage,
+}
+
+/// Part of a streamed answer
+#[derive(Debug, Clone, PartialEq)]
+pub enum Chunk {
+    /// More of the answer's text
+    Text(String),
+    /// Tokens taken so far; see [`TokenUsage::max`]
+    Usage(TokenUsage),
+}
+
+/// A streamed answer
+pub type ChunkStream = BoxStream<'static, Result<Chunk, ProviderError>>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProviderError {
+    /// The API refused the call
+    Status { status: u16, body: String },
+    /// The call, or reading its answer, failed on the way
+    Transport(String),
+    /// No answer, or no next chunk of one, in time
+    Timeout,
+    /// The API ended a streamed answer with an error
+    Interrupted(String),
+    /// The answer is not in the API's format
+    Malformed(String),
+}
+
+impl ProviderError {
+    /// Whether trying again may help: rate limits, server errors and
+    /// calls that never got an answer
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            ProviderError::Status { status, .. } => *status == 429 || *status >= 500,
+            ProviderError::Transport(_) | ProviderError::Timeout | ProviderError::Interrupted(_) => true,
+            ProviderError::Malformed(_) => false,
+        }
+    }
+}
+
+impl fmt::Display for ProviderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProviderError::Status { status, body } => write!(f, "status {}: {}", status, body),
+            ProviderError::Transport(e) => write!(f, "transport error: {}", e),
+            ProviderError::Timeout => write!(f, "timed out"),
+            ProviderError::Interrupted(e) => write!(f, "answer interrupted: {}", e),
+            ProviderError::Malformed(answer) => write!(f, "malformed answer: {}", answer),
+        }
+    }
+}
+
+impl std::error::Error for ProviderError {}
+
+/// A model behind an API
+#[async_trait]
+pub trait AiProvider: Send + Sync {
+    /// Model asked, as priced by [`model_profile`](crate::collective::model_profile)
+    fn model(&self) -> &str;
+
+    /// The whole answer to `prompt`
+    async fn complete(&self, prompt: &str) -> Result<Completion, ProviderError>;
+
+    /// The answer to `prompt` as the model writes it
+    async fn complete_streaming(&self, prompt: &str) -> Result<ChunkStream, ProviderError>;
+}
+
+/// The API serving `model`, configured from the environment: OpenAI
+/// (`OPENAI_API_KEY`, `OPENAI_BASE_URL`) for `gpt-*`, Anthropic
+/// (`ANTHROPIC_API_KEY`, `ANTHROPIC_BASE_URL`) for `claude-*` and Ollama
+/// (`OLLAMA_URL`) for the rest. It is [`Resilient`], with
+/// `AI_TIMEOUT_SECS` and `AI_ATTEMPTS` overriding the defaults.
+pub fn from_env(model: &str) -> Arc<dyn AiProvider> {
+    let var = |name: &str, default: &str| std::env::var(name).unwrap_or_else(|_| default.to_string());
+    if model.starts_with("gpt-") {
+        resilient_from_env(OpenAiProvider::new(&var("OPENAI_BASE_URL", OPENAI_URL), &var("OPENAI_API_KEY", ""), model))
+    } else if model.starts_with("claude-") {
+        let api = AnthropicProvider::new(&var("ANTHROPIC_BASE_URL", ANTHROPIC_URL), &var("ANTHROPIC_API_KEY", ""), model);
+        resilient_from_env(api)
+    } else {
+        resilient_from_env(OllamaProvider::new(&var("OLLAMA_URL", OLLAMA_URL), model))
+    }
+}
+
+fn resilient_from_env<P: AiProvider + 'static>(api: P) -> Arc<dyn AiProvider> {
+    let number = |name: &str| std::env::var(name).ok().and_then(|value| value.parse::<u64>().ok());
+    let timeout = number("AI_TIMEOUT_SECS").map_or(DEFAULT_TIMEOUT, Duration::from_secs);
+    let attempts = number("AI_ATTEMPTS").map_or(DEFAULT_ATTEMPTS, |attempts| attempts as u32);
+    Arc::new(Resilient::new(api).with_timeout(timeout).with_attempts(attempts))
+}
+
+fn transport(e: reqwest::Error) -> ProviderError {
+    ProviderError::Transport(e.to_string())
+}
+
+fn malformed(answer: impl ToString) -> ProviderError {
+    ProviderError::Malformed(answer.to_string())
+}
+
+/// Send a call, turning an error status into an error
+async fn send(request: reqwest::RequestBuilder) -> Result<reqwest::Response, ProviderError> {
+    let response = request.send().await.map_err(transport)?;
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        return Err(ProviderError::Status { status: status.as_u16(), body });
+    }
+    Ok(response)
+}
+
+async fn json_of(response: reqwest::Response) -> Result<Value, ProviderError> {
+    let text = response.text().await.map_err(transport)?;
+    serde_json::from_str(&text).map_err(|_| malformed(text))
+}
+
+/// `field` of a usage object, 0 if missing
+fn count(usage: &Value, field: &str) -> u32 {
+    usage[field].as_u64().unwrap_or_default() as u32
+}
+
+fn text_chunk(text: Option<&Value>) -> Option<Chunk> {
+    text.and_then(Value::as_str).filter(|text| !text.is_empty()).map(|text| Chunk::Text(text.to_string()))
+}
+
+/// Payload of a server-sent event's data line
+fn sse_data(line: &str) -> Option<&str> {
+    line.strip_prefix("data:").map(str::trim_start)
+}
+
+/// The lines of a streamed body, without their line endings
+fn lines(response: reqwest::Response) -> BoxStream<'static, Result<String, ProviderError>> {
+    let body = response.bytes_stream().boxed();
+    stream::unfold((body, Vec::new(), false), |(mut body, mut buffer, mut ended)| async move {
+        loop {
+            if let Some(end) = buffer.iter().position(|&byte| byte == b'\n') {
+                let line: Vec<u8> = buffer.drain(..=end).collect();
+                let line = String::from_utf8_lossy(&line).trim_end().to_string();
+                return Some((Ok(line), (body, buffer, ended)));
+            }
+            if ended {
+                if buffer.is_empty() {
+                    return None;
+                }
+                let line = String::from_utf8_lossy(&buffer).trim_end().to_string();
+                buffer.clear();
+                return Some((Ok(line), (body, buffer, ended)));
+            }
+            match body.next().await {
+                Some(Ok(bytes)) => buffer.extend_from_slice(&bytes),
+                Some(Err(e)) => {
+                    buffer.clear();
+                    return Some((Err(transport(e)), (body, buffer, true)));
+                }
+                None => ended = true,
+            }
+        }
+    })
+    .boxed()
+}
+
+/// The chunks of a streamed body, `parse` turning each line into some
+fn chunks(response: reqwest::Response, parse: fn(&str) -> Result<Vec<Chunk>, ProviderError>) -> ChunkStream {
+    lines(response)
+        .flat_map(move |line| {
+            let chunks = match line.and_then(|line| parse(&line)) {
+                Ok(chunks) => chunks.into_iter().map(Ok).collect(),
+                Err(e) => vec![Err(e)],
+            };
+            stream::iter(chunks)
+        })
+        .boxed()
+}
+
+/// OpenAI's chat completions
+pub struct OpenAiProvider {
+    client: reqwest::Client,
+    base_url: String,
+    api_key: String,
+    model: String,
+}
+
+impl OpenAiProvider {
+    pub fn new(base_url: &str, api_key: &str, model: &str) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key: api_key.to_string(),
+            model: model.to_string(),
+        }
+    }
+
+    fn request(&self, prompt: &str, stream: bool) -> reqwest::RequestBuilder {
+        let mut body = json!({
+            "model": self.model,
+            "messages": [{ "role": "user", "content": prompt }],
+            "stream": stream,
+        });
+        if stream {
+            // Streams only report usage when asked to, in a last chunk
+            // without choices
+            body["stream_options"] = json!({ "include_usage": true });
+        }
+        self.client
+            .post(format!("{}/v1/chat/completions", self.base_url))
+            .bearer_auth(&self.api_key)
+            .json(&body)
+    }
+
+    fn usage(usage: &Value) -> Option<TokenUsage> {
+        usage.is_object().then(|| TokenUsage {
+            prompt_tokens: count(usage, "prompt_tokens"),
+            completion_tokens: count(usage, "completion_tokens"),
+        })
+    }
+
+    fn parse_line(line: &str) -> Result<Vec<Chunk>, ProviderError> {
+        let Some(data) = sse_data(line).filter(|data| *data != "[DONE]") else {
+            return Ok(Vec::new());
+        };
+        let event: Value = serde_json::from_str(data).map_err(|_| malformed(data))?;
+        let mut chunks: Vec<Chunk> = text_chunk(event.pointer("/choices/0/delta/content")).into_iter().collect();
+        chunks.extend(Self::usage(&event["usage"]).map(Chunk::Usage));
+        Ok(chunks)
+    }
+}
+
+#[async_trait]
+impl AiProvider for OpenAiProvider {
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    async fn complete(&self, prompt: &str) -> Result<Completion, ProviderError> {
+        let answer = json_of(send(self.request(prompt, false)).await?).await?;
+        let text = answer.pointer("/choices/0/message/content").and_then(Value::as_str).ok_or_else(|| malformed(&answer))?;
+        Ok(Completion { text: text.to_string(), usage: Self::usage(&answer["usage"]).unwrap_or_default() })
+    }
+
+    async fn complete_streaming(&self, prompt: &str) -> Result<ChunkStream, ProviderError> {
+        Ok(chunks(send(self.request(prompt, true)).await?, Self::parse_line))
+    }
+}
+
+/// Anthropic's messages
+pub struct AnthropicProvider {
+    client: reqwest::Client,
+    base_url: String,
+    api_key: String,
+    model: String,
+}
+
+impl AnthropicProvider {
+    pub fn new(base_url: &str, api_key: &str, model: &str) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key: api_key.to_string(),
+            model: model.to_string(),
+        }
+    }
+
+    fn request(&self, prompt: &str, stream: bool) -> reqwest::RequestBuilder {
+        self.client
+            .post(format!("{}/v1/messages", self.base_url))
+            .header("x-api-key", &self.api_key)
+            .header("anthropic-version", ANTHROPIC_VERSION)
+            .json(&json!({
+                "model": self.model,
+                "max_tokens": ANTHROPIC_MAX_TOKENS,
+                "messages": [{ "role": "user", "content": prompt }],
+                "stream": stream,
+            }))
+    }
+
+    fn usage(usage: &Value) -> TokenUsage {
+        TokenUsage { prompt_tokens: count(usage, "input_tokens"), completion_tokens: count(usage, "output_tokens") }
+    }
+
+    /// The prompt's tokens come with `message_start`, the answer's so far
+    /// with `message_delta`
+    fn parse_line(line: &str) -> Result<Vec<Chunk>, ProviderError> {
+        let Some(data) = sse_data(line) else {
+            return Ok(Vec::new());
+        };
+        let event: Value = serde_json::from_str(data).map_err(|_| malformed(data))?;
+        let chunk = match event["type"].as_str() {
+            Some("message_start") => Some(Chunk::Usage(Self::usage(&event["message"]["usage"]))),
+            Some("content_block_delta") => text_chunk(event.pointer("/delta/text")),
+            Some("message_delta") => Some(Chunk::Usage(Self::usage(&event["usage"]))),
+            Some("error") => return Err(ProviderError::Interrupted(event["error"].to_string())),
+            _ => None,
+        };
+        Ok(chunk.into_iter().collect())
+    }
+}
+
+#[async_trait]
+impl AiProvider for AnthropicProvider {
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    async fn complete(&self, prompt: &str) -> Result<Completion, ProviderError> {
+        let answer = json_of(send(self.request(prompt, false)).await?).await?;
+        let blocks = answer["content"].as_array().ok_or_else(|| malformed(&answer))?;
+        let text = blocks.iter()
+            .filter(|block| block["type"] == "text")
+            .filter_map(|block| block["text"].as_str())
+            .collect();
+        Ok(Completion { text, usage: Self::usage(&answer["usage"]) })
+    }
+
+    async fn complete_streaming(&self, prompt: &str) -> Result<ChunkStream, ProviderError> {
+        Ok(chunks(send(self.request(prompt, true)).await?, Self::parse_line))
+    }
+}
+
+/// A local model served by Ollama
+pub struct OllamaProvider {
+    client: reqwest::Client,
+    base_url: String,
+    model: String,
+}
+
+impl OllamaProvider {
+    pub fn new(base_url: &str, model: &str) -> Self {
+        Self { client: reqwest::Client::new(), base_url: base_url.trim_end_matches('/').to_string(), model: model.to_string() }
+    }
+
+    fn request(&self, prompt: &str, stream: bool) -> reqwest::RequestBuilder {
+        self.client
+            .post(format!("{}/api/generate", self.base_url))
+            .json(&json!({ "model": self.model, "prompt": prompt, "stream": stream }))
+    }
+
+    /// Counted only in the last object; `prompt_eval_count` is left out
+    /// when the prompt was cached
+    fn usage(answer: &Value) -> TokenUsage {
+        TokenUsage { prompt_tokens: count(answer, "prompt_eval_count"), completion_tokens: count(answer, "eval_count") }
+    }
+
+    /// Streams are a JSON object per line
+    fn parse_line(line: &str) -> Result<Vec<Chunk>, ProviderError> {
+        if line.is_empty() {
+            return Ok(Vec::new());
+        }
+        let object: Value = serde_json::from_str(line).map_err(|_| malformed(line))?;
+        if let Some(error) = object["error"].as_str() {
+            return Err(ProviderError::Interrupted(error.to_string()));
+        }
+        let mut chunks: Vec<Chunk> = text_chunk(object.get("response")).into_iter().collect();
+        if object["done"] == true {
+            chunks.push(Chunk::Usage(Self::usage(&object)));
+        }
+        Ok(chunks)
+    }
+}
+
+#[async_trait]
+impl AiProvider for OllamaProvider {
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    async fn complete(&self, prompt: &str) -> Result<Completion, ProviderError> {
+        let answer = json_of(send(self.request(prompt, false)).await?).await?;
+        let text = answer["response"].as_str().ok_or_else(|| malformed(&answer))?;
+        Ok(Completion { text: text.to_string(), usage: Self::usage(&answer) })
+    }
+
+    async fn complete_streaming(&self, prompt: &str) -> Result<ChunkStream, ProviderError> {
+        Ok(chunks(send(self.request(prompt, true)).await?, Self::parse_line))
+    }
+}
+
+/// Gives every call to a provider a timeout and retries the ones that may
+/// succeed another time, waiting longer before each retry
+///
+/// A stream is only retried until it opens: the chunks it already gave
+/// would be repeated. After that, a chunk that takes longer than the
+/// timeout ends the stream with [`ProviderError::Timeout`].
+pub struct Resilient<P> {
+    inner: P,
+    timeout: Duration,
+    attempts: u32,
+    backoff: Duration,
+}
+
+impl<P: AiProvider> Resilient<P> {
+    pub fn new(inner: P) -> Self {
+        Self { inner, timeout: DEFAULT_TIMEOUT, attempts: DEFAULT_ATTEMPTS, backoff: DEFAULT_BACKOFF }
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Calls made at most, the first one included
+    pub fn with_attempts(mut self, attempts: u32) -> Self {
+        self.attempts = attempts.max(1);
+        self
+    }
+
+    async fn retry<T, F, Fut>(&self, mut call: F) -> Result<T, ProviderError>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, ProviderError>>,
+    {
+        let mut backoff = self.backoff;
+        let mut attempt = 1;
+        loop {
+            let result = tokio::time::timeout(self.timeout, call()).await.unwrap_or(Err(ProviderError::Timeout));
+            match result {
+                Err(e) if e.is_retryable() && attempt < self.attempts => {
+                    tracing::warn!("{} failed ({}), attempt {} of {}", self.inner.model(), e, attempt, self.attempts);
+                    tokio::time::sleep(backoff).await;
+                    backoff *= 2;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl<P: AiProvider> AiProvider for Resilient<P> {
+    fn model(&self) -> &str {
+        self.inner.model()
+    }
+
+    async fn complete(&self, prompt: &str) -> Result<Completion, ProviderError> {
+        self.retry(|| self.inner.complete(prompt)).await
+    }
+
+    async fn complete_streaming(&self, prompt: &str) -> Result<ChunkStream, ProviderError> {
+        let answer = self.retry(|| self.inner.complete_streaming(prompt)).await?;
+        let timeout = self.timeout;
+        Ok(stream::unfold(Some(answer), move |answer| async move {
+            let mut answer = answer?;
+            match tokio::time::timeout(timeout, answer.next()).await {
+                Ok(Some(chunk)) => Some((chunk, Some(answer))),
+                Ok(None) => None,
+                Err(_) => Some((Err(ProviderError::Timeout), None)),
+            }
+        })
+        .boxed())
+    }
+}
+
+/// What a model is asked for each decision
+pub fn decision_prompt(state: &GameState, player_id: &str) -> String {
+    let example = match state.game_type {
+        GameType::ConsciousnessEmergence => GameAction::PlaceNeuron { x: 0, y: 0 },
+        GameType::MinorityGame | GameType::OracleParadox => GameAction::ChooseSide { side: 0 },
+        GameType::SemanticShapeshifter => GameAction::SubmitWord { word: "word".to_string() },
+        GameType::CollectiveMaze => GameAction::Move { direction: Direction::North },
+    };
+    let mut scores: Vec<String> = state.players.values().map(|player| format!("{} {}", player.id, player.score)).collect();
+    scores.sort();
+    format!(
+        "You are player {} of a {:?} game, in round {} of {}. Scores: {}.\n\
+         Start your answer with your action as a JSON object, such as {}, then explain it.\n\
+         The game: {}",
+        player_id,
+        state.game_type,
+        state.round,
+        state.max_rounds,
+        scores.join(", "),
+        serde_json::to_string(&example).unwrap_or_default(),
+        serde_json::to_string(state).unwrap_or_default(),
+    )
+}
+
+/// The action a model's answer starts with, once its JSON is complete
+pub fn parse_decision(answer: &str) -> Option<GameAction> {
+    let start = answer.find('{')?;
+    serde_json::Deserializer::from_str(&answer[start..]).into_iter::<GameAction>().next()?.ok()
+}
+
+/// Asks a model through its API; see the [module docs](self)
+pub struct ModelDecisionProvider {
+    provider: Arc<dyn AiProvider>,
+    runtime: Handle,
+    /// Answers still being read after their action was played, each ending
+    /// with the tokens it took beyond what was charged for the action
+    streaming: Vec<JoinHandle<TokenUsage>>,
+}
+
+impl ModelDecisionProvider {
+    /// `runtime` reads the answers; [`decide`](DecisionProvider::decide)
+    /// blocks on it, so call it from outside the runtime
+    pub fn new(provider: Arc<dyn AiProvider>, runtime: Handle) -> Self {
+        Self { provider, runtime, streaming: Vec::new() }
+    }
+
+    /// A provider per model, connected by `connect` and reading answers on
+    /// the current runtime
+    pub fn factory(connect: impl Fn(&str) -> Arc<dyn AiProvider> + Send + Sync + 'static) -> ProviderFactory {
+        let runtime = Handle::current();
+        Arc::new(move |model, _seed| {
+            Box::new(ModelDecisionProvider::new(connect(model), runtime.clone())) as Box<dyn DecisionProvider>
+        })
+    }
+}
+
+/// Stream an answer until its action parses: the action, the tokens taken
+/// by then and the rest of the answer
+async fn read_decision(
+    provider: &dyn AiProvider,
+    prompt: &str,
+) -> (Option<GameAction>, TokenUsage, Option<ChunkStream>) {
+    let mut usage = TokenUsage::default();
+    let mut answer = match provider.complete_streaming(prompt).await {
+        Ok(answer) => answer,
+        Err(e) => {
+            tracing::warn!("{} did not answer: {}", provider.model(), e);
+            return (None, usage, None);
+        }
+    };
+    let mut text = String::new();
+    while let Some(chunk) = answer.next().await {
+        match chunk {
+            Ok(Chunk::Text(more)) => {
+                text.push_str(&more);
+                if let Some(action) = parse_decision(&text) {
+                    return (Some(action), usage, Some(answer));
+                }
+            }
+            Ok(Chunk::Usage(so_far)) => usage = usage.max(so_far),
+            Err(e) => {
+                tracing::warn!("{} stopped answering: {}", provider.model(), e);
+                break;
+            }
+        }
+    }
+    (None, usage, None)
+}
+
+/// Read the rest of an answer: the tokens it took beyond `charged`
+async fn drain(mut rest: ChunkStream, charged: TokenUsage) -> TokenUsage {
+    let mut usage = charged;
+    while let Some(chunk) = rest.next().await {
+        match chunk {
+            Ok(Chunk::Usage(so_far)) => usage = usage.max(so_far),
+            Ok(Chunk::Text(_)) => {}
+            Err(_) => break,
+        }
+    }
+    usage.since(charged)
+}
+
+impl DecisionProvider for ModelDecisionProvider {
+    fn model(&self) -> &str {
+        self.provider.model()
+    }
+
+    fn decide(&mut self, state: &GameState, player_id: &str) -> ProviderDecision {
+        let started = Instant::now();
+        let prompt = decision_prompt(state, player_id);
+        let (action, charged, rest) = self.runtime.block_on(read_decision(self.provider.as_ref(), &prompt));
+        if let Some(rest) = rest {
+            self.streaming.push(self.runtime.spawn(drain(rest, charged)));
+        }
+        ProviderDecision {
+            action,
+            prompt_tokens: charged.prompt_tokens,
+            completion_tokens: charged.completion_tokens,
+            latency_ms: started.elapsed().as_millis() as u64,
+        }
+    }
+
+    fn late_usage(&mut self, wait: bool) -> TokenUsage {
+        let mut late = TokenUsage::default();
+        let mut still_streaming = Vec::new();
+        for answer in self.streaming.drain(..) {
+            if wait || answer.is_finished() {
+                late += self.runtime.block_on(answer).unwrap_or_default();
+            } else {
+                still_streaming.push(answer);
+            }
+        }
+        self.streaming = still_streaming;
+        late
+    }
+}
+
+impl Drop for ModelDecisionProvider {
+    /// Nobody is left to charge for the answers still being read
+    fn drop(&mut self) {
+        for answer in &self.streaming {
+            answer.abort();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collective::{model_profile, Collective, Coordination, UsageMeter};
+    use crate::bots::Bot;
+    use crate::{new_game, GameStatus};
+    use std::sync::Mutex;
+    use tokio::sync::mpsc;
+    use wiremock::matchers::{body_partial_json, header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    async fn collect(answer: ChunkStream) -> (String, TokenUsage) {
+        let chunks: Vec<Chunk> = answer.map(Result::unwrap).collect().await;
+        let mut text = String::new();
+        let mut usage = TokenUsage::default();
+        for chunk in chunks {
+            match chunk {
+                Chunk::Text(more) => text.push_str(&more),
+                Chunk::Usage(so_far) => usage = usage.max(so_far),
+            }
+        }
+        (text, usage)
+    }
+
+    fn usage(prompt_tokens: u32, completion_tokens: u32) -> TokenUsage {
+        TokenUsage { prompt_tokens, completion_tokens }
+    }
+
+    #[tokio::test]
+    async fn test_openai_usage_comes_in_a_last_chunk_without_choices() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/chat/completions"))
+            .and(header("authorization", "Bearer sk-test"))
+            .and(body_partial_json(json!({ "stream": false })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "chatcmpl-1",
+                "object": "chat.completion",
+                "model": "gpt-4o",
+                "choices": [{
+                    "index": 0,
+                    "message": { "role": "assistant", "content": "{\"action\":\"choose_side\",\"side\":1}" },
+                    "finish_reason": "stop"
+                }],
+                "usage": { "prompt_tokens": 412, "completion_tokens": 13, "total_tokens": 425 }
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/v1/chat/completions"))
+            .and(body_partial_json(json!({ "stream": true, "stream_options": { "include_usage": true } })))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(
+                concat!(
+                    "data: {\"id\":\"chatcmpl-2\",\"choices\":[{\"index\":0,\"delta\":{\"role\":\"assistant\",\"content\":\"\"}}],\"usage\":null}\n\n",
+                    "data: {\"id\":\"chatcmpl-2\",\"choices\":[{\"index\":0,\"delta\":{\"content\":\"{\\\"action\\\":\"}}],\"usage\":null}\n\n",
+                    "data: {\"id\":\"chatcmpl-2\",\"choices\":[{\"index\":0,\"delta\":{\"content\":\"\\\"choose_side\\\",\\\"side\\\":1}\"}}],\"usage\":null}\n\n",
+                    "data: {\"id\":\"chatcmpl-2\",\"choices\":[{\"index\":0,\"delta\":{},\"finish_reason\":\"stop\"}],\"usage\":null}\n\n",
+                    "data: {\"id\":\"chatcmpl-2\",\"choices\":[],\"usage\":{\"prompt_tokens\":412,\"completion_tokens\":13,\"total_tokens\":425}}\n\n",
+                    "data: [DONE]\n\n",
+                ),
+                "text/event-stream",
+            ))
+            .mount(&server)
+            .await;
+
+        let openai = OpenAiProvider::new(&server.uri(), "sk-test", "gpt-4o");
+        let whole = openai.complete("play").await.unwrap();
+        assert_eq!(whole.usage, usage(412, 13));
+        assert!(matches!(parse_decision(&whole.text), Some(GameAction::ChooseSide { side: 1 })));
+
+        let (text, streamed) = collect(openai.complete_streaming("play").await.unwrap()).await;
+        assert_eq!((text, streamed), (whole.text, whole.usage));
+    }
+
+    #[tokio::test]
+    async fn test_anthropic_usage_is_split_between_start_and_delta() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/messages"))
+            .and(header("x-api-key", "sk-ant-test"))
+            .and(header("anthropic-version", ANTHROPIC_VERSION))
+            .and(body_partial_json(json!({ "stream": false, "max_tokens": ANTHROPIC_MAX_TOKENS })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "msg_1",
+                "type": "message",
+                "role": "assistant",
+                "model": "claude-opus-4",
+                "content": [{ "type": "text", "text": "{\"action\":\"place_neuron\",\"x\":2,\"y\":3} Center." }],
+                "stop_reason": "end_turn",
+                "usage": { "input_tokens": 530, "output_tokens": 21 }
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/v1/messages"))
+            .and(body_partial_json(json!({ "stream": true })))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(
+                concat!(
+                    "event: message_start\n",
+                    "data: {\"type\":\"message_start\",\"message\":{\"id\":\"msg_2\",\"content\":[],\"usage\":{\"input_tokens\":530,\"output_tokens\":1}}}\n\n",
+                    "event: content_block_start\n",
+                    "data: {\"type\":\"content_block_start\",\"index\":0,\"content_block\":{\"type\":\"text\",\"text\":\"\"}}\n\n",
+                    "event: ping\n",
+                    "data: {\"type\":\"ping\"}\n\n",
+                    "event: content_block_delta\n",
+                    "data: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"text_delta\",\"text\":\"{\\\"action\\\":\\\"place_neuron\\\",\"}}\n\n",
+                    "event: content_block_delta\n",
+                    "data: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"text_delta\",\"text\":\"\\\"x\\\":2,\\\"y\\\":3} Center.\"}}\n\n",
+                    "event: content_block_stop\n",
+                    "data: {\"type\":\"content_block_stop\",\"index\":0}\n\n",
+                    "event: message_delta\n",
+                    "data: {\"type\":\"message_delta\",\"delta\":{\"stop_reason\":\"end_turn\"},\"usage\":{\"output_tokens\":21}}\n\n",
+                    "event: message_stop\n",
+                    "data: {\"type\":\"message_stop\"}\n\n",
+                ),
+                "text/event-stream",
+            ))
+            .mount(&server)
+            .await;
+
+        let anthropic = AnthropicProvider::new(&server.uri(), "sk-ant-test", "claude-opus-4");
+        let whole = anthropic.complete("play").await.unwrap();
+        assert_eq!(whole.usage, usage(530, 21));
+        assert!(matches!(parse_decision(&whole.text), Some(GameAction::PlaceNeuron { x: 2, y: 3 })));
+
+        let (text, streamed) = collect(anthropic.complete_streaming("play").await.unwrap()).await;
+        assert_eq!((text, streamed), (whole.text, whole.usage));
+
+        // Overloaded mid-answer
+        let error = "data: {\"type\":\"error\",\"error\":{\"type\":\"overloaded_error\",\"message\":\"Overloaded\"}}";
+        assert!(matches!(AnthropicProvider::parse_line(error), Err(ProviderError::Interrupted(e)) if e.contains("overloaded_error")));
+    }
+
+    #[tokio::test]
+    async fn test_ollama_leaves_out_the_prompt_count_of_a_cached_prompt() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/api/generate"))
+            .and(body_partial_json(json!({ "model": "llama-3-8b", "stream": false })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "model": "llama-3-8b",
+                "response": "{\"action\":\"choose_side\",\"side\":0}",
+                "done": true,
+                "prompt_eval_count": 388,
+                "eval_count": 12
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/api/generate"))
+            .and(body_partial_json(json!({ "stream": true })))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(
+                concat!(
+                    "{\"model\":\"llama-3-8b\",\"response\":\"{\\\"action\\\":\\\"choose_side\\\",\",\"done\":false}\n",
+                    "{\"model\":\"llama-3-8b\",\"response\":\"\\\"side\\\":0}\",\"done\":false}\n",
+                    "{\"model\":\"llama-3-8b\",\"response\":\"\",\"done\":true,\"eval_count\":12}",
+                ),
+                "application/x-ndjson",
+            ))
+            .mount(&server)
+            .await;
+
+        let ollama = OllamaProvider::new(&server.uri(), "llama-3-8b");
+        let whole = ollama.complete("play").await.unwrap();
+        assert_eq!(whole.usage, usage(388, 12));
+
+        let (text, streamed) = collect(ollama.complete_streaming("play").await.unwrap()).await;
+        assert_eq!((text, streamed), (whole.text, usage(0, 12)));
+
+        assert_eq!(
+            OllamaProvider::parse_line("{\"error\":\"model not found\"}"),
+            Err(ProviderError::Interrupted("model not found".to_string()))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_resilient_retries_what_may_succeed_and_times_out() {
+        let server = MockServer::start().await;
+        let answer = json!({ "response": "{}", "done": true, "prompt_eval_count": 5, "eval_count": 1 });
+        Mock::given(path("/api/generate"))
+            .respond_with(ResponseTemplate::new(503))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(path("/api/generate"))
+            .respond_with(ResponseTemplate::new(429))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(path("/api/generate"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(&answer))
+            .mount(&server)
+            .await;
+        let resilient = |model| {
+            Resilient { backoff: Duration::from_millis(1), ..Resilient::new(OllamaProvider::new(&server.uri(), model)) }
+        };
+
+        assert_eq!(resilient("llama-3-8b").complete("play").await.unwrap().usage, usage(5, 1));
+        assert_eq!(server.received_requests().await.unwrap().len(), 3);
+
+        // Not found is not retried; the last attempt's error is kept
+        server.reset().await;
+        Mock::given(path("/api/generate")).respond_with(ResponseTemplate::new(404)).mount(&server).await;
+        let refused = resilient("llama-3-8b").complete("play").await;
+        assert!(matches!(refused, Err(ProviderError::Status { status: 404, .. })));
+        assert_eq!(server.received_requests().await.unwrap().len(), 1);
+
+        server.reset().await;
+        Mock::given(path("/api/generate"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(&answer).set_delay(Duration::from_millis(500)))
+            .mount(&server)
+            .await;
+        let slow = resilient("llama-3-8b").with_timeout(Duration::from_millis(50)).with_attempts(2);
+        assert_eq!(slow.complete("play").await, Err(ProviderError::Timeout));
+        assert_eq!(slow.complete_streaming("play").await.err(), Some(ProviderError::Timeout));
+        assert_eq!(server.received_requests().await.unwrap().len(), 4);
+    }
+
+    /// Streams the chunks the test sends it
+    struct Scripted {
+        answer: Mutex<Option<mpsc::UnboundedReceiver<Result<Chunk, ProviderError>>>>,
+    }
+
+    #[async_trait]
+    impl AiProvider for Scripted {
+        fn model(&self) -> &str {
+            "claude-haiku-3"
+        }
+
+        async fn complete(&self, _prompt: &str) -> Result<Completion, ProviderError> {
+            Err(ProviderError::Malformed("only streams".to_string()))
+        }
+
+        async fn complete_streaming(&self, _prompt: &str) -> Result<ChunkStream, ProviderError> {
+            let answer = self.answer.lock().unwrap().take().ok_or(ProviderError::Timeout)?;
+            Ok(stream::unfold(answer, |mut answer| async move { answer.recv().await.map(|chunk| (chunk, answer)) }).boxed())
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_the_action_is_played_while_the_reasoning_streams() {
+        let (chunks, answer) = mpsc::unbounded_channel();
+        let provider = Arc::new(Scripted { answer: Mutex::new(Some(answer)) });
+        let meter = UsageMeter::default();
+        let agent = ModelDecisionProvider::new(provider, Handle::current());
+        let mut collective = Collective::new(vec![Box::new(agent)], Coordination::Vote, meter.clone());
+
+        for chunk in [
+            Chunk::Usage(usage(600, 1)),
+            Chunk::Text("{\"action\": \"choose_side\",".to_string()),
+            Chunk::Text(" \"side\": 1} Most players".to_string()),
+        ] {
+            chunks.send(Ok(chunk)).unwrap();
+        }
+        let mut state = new_game(GameType::MinorityGame, 5, Some(1));
+        state.status = GameStatus::Running;
+        let (mut collective, action) = tokio::task::spawn_blocking(move || {
+            let action = collective.choose_action(&state, "collective");
+            (collective, action)
+        })
+        .await
+        .unwrap();
+        assert!(matches!(action, Some(GameAction::ChooseSide { side: 1 })));
+        let played = meter.lock().unwrap().clone();
+        assert_eq!((played.decisions, played.calls, played.prompt_tokens, played.completion_tokens), (1, 1, 600, 1));
+
+        // The rest of the answer is charged once it ends
+        chunks.send(Ok(Chunk::Text(" will pick side 0.".to_string()))).unwrap();
+        chunks.send(Ok(Chunk::Usage(usage(0, 80)))).unwrap();
+        drop(chunks);
+        tokio::task::spawn_blocking(move || collective.finish()).await.unwrap();
+        let usage = meter.lock().unwrap().clone();
+        assert_eq!((usage.calls, usage.prompt_tokens, usage.completion_tokens), (1, 600, 80));
+        let haiku = model_profile("claude-haiku-3");
+        assert!((usage.cost_usd - haiku.cost(600, 80)).abs() < 1e-12);
+        let by_model = &usage.by_model["claude-haiku-3"];
+        assert_eq!((by_model.calls, by_model.prompt_tokens, by_model.completion_tokens), (1, 600, 80));
+    }
+
+    #[test]
+    fn test_decisions_parse_once_their_json_is_complete() {
+        assert!(parse_decision("{\"action\":\"choose_side\",\"side\":").is_none());
+        assert!(matches!(
+            parse_decision("Sure: {\"action\":\"move\",\"direction\":\"north\"} because"),
+            Some(GameAction::Move { direction: Direction::North })
+        ));
+        assert!(parse_decision("{\"action\":\"pass\"}").is_none());
+
+        let state = new_game(GameType::CollectiveMaze, 5, Some(1));
+        assert!(decision_prompt(&state, "collective").contains("{\"action\":\"move\",\"direction\":\"north\"}"));
+    }
+}
@@ -0,0 +1,238 @@
+//! Per-game analytics built from the public game state
+//!
+//! Everything here is derived from what players can already see, so hidden
+//! information such as the shapeshifter's current target, unrevealed
+//! oracle predictions or the collective maze's layout never leaks.
+
+use serde::Serialize;
+use std::collections::HashMap;
+
+use crate::maze::MazeRound;
+use crate::oracle::ParadoxStep;
+use crate::{GameState, GameType};
+
+/// Scores and game-specific breakdowns for one game
+#[derive(Debug, Clone, Serialize)]
+pub struct GameAnalytics {
+    pub game_id: String,
+    pub game_type: GameType,
+    pub round: u32,
+    /// Highest score first
+    pub scores: Vec<PlayerSummary>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shapeshifter: Option<ShapeshifterAnalytics>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub oracle: Option<OracleAnalytics>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub maze: Option<MazeAnalytics>,
+}
+
+/// One player's standing
+#[derive(Debug, Clone, Serialize)]
+pub struct PlayerSummary {
+    pub player_id: String,
+    pub name: String,
+    pub score: i32,
+    /// Accepted actions
+    pub decisions: usize,
+}
+
+/// How the shapeshifter's target moved and how close each player got
+#[derive(Debug, Clone, Serialize)]
+pub struct ShapeshifterAnalytics {
+    /// Revealed targets, oldest first
+    pub drift: Vec<DriftStep>,
+    /// Every scored submission, by player, oldest first
+    pub similarity_history: HashMap<String, Vec<SimilarityPoint>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DriftStep {
+    pub round: u32,
+    pub target: String,
+    /// Similarity to the previous round's target
+    pub similarity_to_previous: Option<f32>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SimilarityPoint {
+    pub round: u32,
+    pub word: String,
+    pub similarity: f32,
+    pub points: i32,
+    pub repeat: bool,
+}
+
+/// How well players predicted each other, and how publishing the
+/// predictions changed what they did
+#[derive(Debug, Clone, Serialize)]
+pub struct OracleAnalytics {
+    /// Each round's revealed consensus against behaviour then and after,
+    /// oldest first
+    pub paradox: Vec<ParadoxStep>,
+    /// Mean [`divergence`](ParadoxStep::divergence) over `paradox`: how many
+    /// percentage points revealed predictions pushed the next round's
+    /// behaviour away from themselves; `None` before there is any
+    pub paradox_index: Option<f32>,
+    /// Every round each player took part in, by player, oldest first
+    pub predictions: HashMap<String, Vec<PredictionPoint>>,
+    /// Rounds forfeited by not revealing, by player
+    pub forfeits: HashMap<String, usize>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PredictionPoint {
+    pub round: u32,
+    /// `None` if the prediction was not revealed
+    pub prediction: Option<u8>,
+    /// Percentage of side 0 the prediction was scored against
+    pub share: Option<f32>,
+    pub side: Option<u8>,
+    pub points: i32,
+}
+
+/// How much the players talked against how far they got
+#[derive(Debug, Clone, Serialize)]
+pub struct MazeAnalytics {
+    /// Each finished round's messages against the tiles visited and goals
+    /// reached by its end, oldest first
+    pub rounds: Vec<MazeRound>,
+    /// What each player sent and achieved, by player
+    pub players: HashMap<String, MazeTraffic>,
+    /// Share of the maze's open tiles some avatar stood on, from 0 to 1
+    pub coverage: f32,
+    pub goals: usize,
+    pub goals_reached: usize,
+    /// Round the last goal was reached in
+    pub completed_round: Option<u32>,
+    /// Message bytes sent per point scored by anyone; `None` before there
+    /// are any points
+    pub bytes_per_point: Option<f32>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct MazeTraffic {
+    pub messages: usize,
+    pub message_bytes: usize,
+    pub goals_reached: usize,
+    pub score: i32,
+}
+
+impl GameAnalytics {
+    pub fn of(state: &GameState) -> Self {
+        let mut decisions: HashMap<&str, usize> = HashMap::new();
+        for decision in &state.decisions {
+            *decisions.entry(decision.player_id.as_str()).or_default() += 1;
+        }
+        let mut scores: Vec<PlayerSummary> = state.players.values()
+            .map(|p| PlayerSummary {
+                player_id: p.id.clone(),
+                name: p.name.clone(),
+                score: p.score,
+                decisions: decisions.get(p.id.as_str()).copied().unwrap_or_default(),
+            })
+            .collect();
+        scores.sort_by(|a, b| b.score.cmp(&a.score).then_with(|| a.player_id.cmp(&b.player_id)));
+
+        let shapeshifter = matches!(state.game_type, GameType::SemanticShapeshifter)
+            .then(|| ShapeshifterAnalytics::of(state));
+        let oracle = matches!(state.game_type, GameType::OracleParadox)
+            .then(|| OracleAnalytics::of(state));
+        let maze = matches!(state.game_type, GameType::CollectiveMaze)
+            .then(|| MazeAnalytics::of(state));
+
+        Self {
+            game_id: state.id.clone(),
+            game_type: state.game_type.clone(),
+            round: state.round,
+            scores,
+            shapeshifter,
+            oracle,
+            maze,
+        }
+    }
+}
+
+impl ShapeshifterAnalytics {
+    fn of(state: &GameState) -> Self {
+        let rounds = &state.shapeshifter.rounds;
+        let drift = rounds.iter()
+            .map(|r| DriftStep { round: r.round, target: r.target.clone(), similarity_to_previous: r.drift })
+            .collect();
+
+        let mut similarity_history: HashMap<String, Vec<SimilarityPoint>> = HashMap::new();
+        for round in rounds {
+            for score in &round.scores {
+                similarity_history.entry(score.player_id.clone()).or_default().push(SimilarityPoint {
+                    round: round.round,
+                    word: score.word.clone(),
+                    similarity: score.similarity,
+                    points: score.points,
+                    repeat: score.repeat,
+                });
+            }
+        }
+
+        Self { drift, similarity_history }
+    }
+}
+
+impl OracleAnalytics {
+    fn of(state: &GameState) -> Self {
+        let paradox = state.oracle.paradox();
+        let paradox_index = (!paradox.is_empty())
+            .then(|| paradox.iter().map(|step| step.divergence).sum::<f32>() / paradox.len() as f32);
+
+        let mut predictions: HashMap<String, Vec<PredictionPoint>> = HashMap::new();
+        let mut forfeits: HashMap<String, usize> = HashMap::new();
+        for round in &state.oracle.rounds {
+            for score in &round.scores {
+                predictions.entry(score.player_id.clone()).or_default().push(PredictionPoint {
+                    round: round.round,
+                    prediction: score.prediction,
+                    share: round.share,
+                    side: score.side,
+                    points: score.points,
+                });
+                if score.forfeited {
+                    *forfeits.entry(score.player_id.clone()).or_default() += 1;
+                }
+            }
+        }
+
+        Self { paradox, paradox_index, predictions, forfeits }
+    }
+}
+
+impl MazeAnalytics {
+    fn of(state: &GameState) -> Self {
+        let maze = &state.maze;
+        let mut players: HashMap<String, MazeTraffic> = state.players.values()
+            .map(|p| (p.id.clone(), MazeTraffic { score: p.score, ..Default::default() }))
+            .collect();
+        for message in &maze.messages {
+            let traffic = players.entry(message.from.clone()).or_default();
+            traffic.messages += 1;
+            traffic.message_bytes += message.text.len();
+        }
+        for goal in &maze.reached {
+            players.entry(goal.player_id.clone()).or_default().goals_reached += 1;
+        }
+
+        let open = maze.tiles.iter().filter(|tile| tile.is_passable()).count();
+        let coverage = if open == 0 { 0.0 } else { maze.visited.len() as f32 / open as f32 };
+        let bytes: usize = maze.messages.iter().map(|m| m.text.len()).sum();
+        let points: i32 = state.players.values().map(|p| p.score).sum();
+        let bytes_per_point = (points > 0).then(|| bytes as f32 / points as f32);
+
+        Self {
+            rounds: maze.rounds.clone(),
+            players,
+            coverage,
+            goals: maze.goals,
+            goals_reached: maze.reached.len(),
+            completed_round: maze.is_complete().then(|| maze.reached.last().map(|goal| goal.round)).flatten(),
+            bytes_per_point,
+        }
+    }
+}
@@ -0,0 +1,789 @@
+//! Cost and performance benchmarks of collectives against single models
+//!
+//! A [`BenchmarkPlan`] lists collective presets, opponent models and game
+//! types. Every collective plays every opponent at every game type
+//! `matches_per_cell` times, one on one. Each match is seeded from the
+//! plan's seed and its place in the matrix, so with [`MockProvider`]s a
+//! plan always plays out the same way.
+//!
+//! The [`BenchmarkReport`] gives, per cell of the matrix and per collective
+//! over all of them, the win rate, average score, what the models cost
+//! both sides, decisions per second of decision time, and emergence
+//! events: the consciousness milestones (every tenth of the level) the
+//! collective's own moves reached first.
+//!
+//! A run keeps its state in a directory:
+//!
+//! - `plan.json`: the plan being run
+//! - `matches.jsonl`: a [`MatchResult`] per finished match, appended as
+//!   matches finish
+//! - `report.json` and `report.csv`: the report, written when the run ends
+//!
+//! Running the plan again in the same directory resumes it: finished
+//! matches are read back and only the others are played. A result cut
+//! short by the interruption is dropped and its match played again.
+//! Matches run in parallel, at most [`BenchmarkRunner::with_concurrency`]
+//! at once.
+//!
+//! From the command line:
+//!
+//! ```text
+//! ai-genius-game benchmark <plan.json> <run-dir> [--concurrency N] [--live]
+//! ```
+//!
+//! `--live` plays the models themselves, through the APIs
+//! [`ai_provider::from_env`] configures, instead of [`MockProvider`]s. Every
+//! model is asked once before the first match, as one that cannot answer
+//! would pass every turn of its matches.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+use crate::ai_provider::{self, ModelDecisionProvider};
+use crate::bots::{Bot, OracleBot};
+use crate::collective::{Collective, CollectiveConfig, MockProvider, ProviderFactory, Usage, UsageMeter};
+use crate::maze;
+use crate::{
+    generate_player_color, new_game, rules, GameState, GameStatus, GameType, Player, PlayerType,
+    CONSCIOUSNESS_THRESHOLD,
+};
+
+/// Matches played at once unless the runner is told otherwise
+pub const DEFAULT_CONCURRENCY: usize = 4;
+
+/// Rounds per match unless the plan says otherwise
+pub const DEFAULT_MAX_ROUNDS: u32 = 20;
+
+const PLAN_FILE: &str = "plan.json";
+const MATCHES_FILE: &str = "matches.jsonl";
+const REPORT_JSON_FILE: &str = "report.json";
+const REPORT_CSV_FILE: &str = "report.csv";
+
+/// Player ids in every benchmark match
+const COLLECTIVE_ID: &str = "collective";
+const OPPONENT_ID: &str = "opponent";
+
+/// Mixed into a match's seed for each side's agents
+const COLLECTIVE_STREAM: u64 = 0x434f_4c4c_4543_5456;
+const OPPONENT_STREAM: u64 = 0x4f50_504f_4e45_4e54;
+
+/// Columns of [`BenchmarkReport::to_csv`]
+const CSV_HEADER: &str = "collective,opponent,game_type,matches,wins,draws,losses,win_rate,\
+average_score,average_opponent_score,cost_usd,opponent_cost_usd,decisions_per_second,emergence_events";
+
+/// Collectives, opponents and game types to play, and how often
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BenchmarkPlan {
+    pub collectives: Vec<CollectiveConfig>,
+    /// Models playing alone
+    pub opponents: Vec<String>,
+    pub game_types: Vec<GameType>,
+    pub matches_per_cell: u32,
+    #[serde(default = "default_max_rounds")]
+    pub max_rounds: u32,
+    #[serde(default)]
+    pub seed: u64,
+}
+
+fn default_max_rounds() -> u32 {
+    DEFAULT_MAX_ROUNDS
+}
+
+/// A match's place in the matrix
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct MatchKey {
+    pub collective: CollectiveConfig,
+    pub opponent: String,
+    /// As in [`game_type_name`]
+    pub game_type: String,
+    /// Which of the cell's matches, from 0
+    pub index: u32,
+}
+
+/// Name of a game type in keys and reports
+pub fn game_type_name(game_type: &GameType) -> String {
+    format!("{:?}", game_type)
+}
+
+/// A planned match
+#[derive(Debug, Clone)]
+pub struct PlannedMatch {
+    pub key: MatchKey,
+    pub game_type: GameType,
+    pub seed: u64,
+}
+
+impl BenchmarkPlan {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.collectives.is_empty() || self.opponents.is_empty() || self.game_types.is_empty() {
+            return Err("Plan needs at least one collective, opponent and game type".to_string());
+        }
+        if self.matches_per_cell == 0 || self.max_rounds == 0 {
+            return Err("Plan needs at least one match per cell and one round per match".to_string());
+        }
+        Ok(())
+    }
+
+    /// Every match, collective by collective, then opponent by opponent,
+    /// then game type by game type
+    pub fn matches(&self) -> Vec<PlannedMatch> {
+        let mut matches = Vec::new();
+        for &collective in &self.collectives {
+            for opponent in &self.opponents {
+                for game_type in &self.game_types {
+                    let cell = (matches.len() / self.matches_per_cell as usize) as u64;
+                    for index in 0..self.matches_per_cell {
+                        matches.push(PlannedMatch {
+                            key: MatchKey {
+                                collective,
+                                opponent: opponent.clone(),
+                                game_type: game_type_name(game_type),
+                                index,
+                            },
+                            game_type: game_type.clone(),
+                            seed: match_seed(self.seed, cell, index),
+                        });
+                    }
+                }
+            }
+        }
+        matches
+    }
+}
+
+/// Seed of a match, spread out with splitmix64 so that neighbouring
+/// matches play nothing alike
+fn match_seed(seed: u64, cell: u64, index: u32) -> u64 {
+    let mut z = seed ^ (cell << 32 | index as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// How a match went for the collective
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    Win,
+    /// The rounds ran out with the scores level
+    Draw,
+    Loss,
+}
+
+/// One finished match
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MatchResult {
+    #[serde(flatten)]
+    pub key: MatchKey,
+    pub seed: u64,
+    pub outcome: Outcome,
+    pub score: i32,
+    pub opponent_score: i32,
+    /// Rounds played
+    pub rounds: u32,
+    pub usage: Usage,
+    pub opponent_usage: Usage,
+    pub emergence_events: u32,
+}
+
+/// Play one match to the end
+pub fn play_match(planned: &PlannedMatch, max_rounds: u32, providers: &ProviderFactory) -> Result<MatchResult, String> {
+    let key = &planned.key;
+    let seed = planned.seed;
+    let mut state = new_game(planned.game_type.clone(), max_rounds, Some(seed));
+
+    let meters = (UsageMeter::default(), UsageMeter::default());
+    let collective = Collective::of(key.collective, providers, seed ^ COLLECTIVE_STREAM, meters.0.clone());
+    let opponent = Collective::single(&key.opponent, providers, seed ^ OPPONENT_STREAM, meters.1.clone());
+    let mut players: Vec<(&str, Box<dyn Bot>)> = vec![
+        (COLLECTIVE_ID, Box::new(OracleBot::new(Box::new(collective), seed ^ COLLECTIVE_STREAM))),
+        (OPPONENT_ID, Box::new(OracleBot::new(Box::new(opponent), seed ^ OPPONENT_STREAM))),
+    ];
+    for (id, player_type, name) in [
+        (
+            COLLECTIVE_ID,
+            PlayerType::HAL9Collective { agent_count: key.collective.models().len() as u32 },
+            format!("HAL9 {}", key.collective.as_str()),
+        ),
+        (OPPONENT_ID, PlayerType::SingleAI { model: key.opponent.clone() }, format!("{} Solo", key.opponent)),
+    ] {
+        let player = Player {
+            id: id.to_string(),
+            name,
+            player_type,
+            score: 0,
+            neurons_placed: 0,
+            color: generate_player_color(&state.players),
+        };
+        rules::add_player(&mut state, player);
+    }
+    rules::start_game(&mut state)?;
+
+    // Consciousness milestones reached so far, by anyone
+    let mut reached = 0;
+    let mut emergence_events = 0;
+    while state.status == GameStatus::Running {
+        for (id, bot) in players.iter_mut() {
+            bot.observe(&maze::view(&state, id));
+        }
+        for (id, bot) in players.iter_mut() {
+            if state.status != GameStatus::Running {
+                break;
+            }
+            let Some(action) = bot.choose_action(&maze::view(&state, id), id) else {
+                continue;
+            };
+            if rules::apply_action(&mut state, id, action).is_err() {
+                continue;
+            }
+            let milestone = (state.consciousness_level * 10.0).floor() as u32;
+            if milestone > reached {
+                if *id == COLLECTIVE_ID {
+                    emergence_events += milestone - reached;
+                }
+                reached = milestone;
+            }
+        }
+        rules::end_phase(&mut state);
+    }
+    for (_, bot) in players.iter_mut() {
+        bot.finish();
+    }
+
+    let score = score_of(&state, COLLECTIVE_ID);
+    let opponent_score = score_of(&state, OPPONENT_ID);
+    let outcome = if score == opponent_score && state.consciousness_level < CONSCIOUSNESS_THRESHOLD {
+        Outcome::Draw
+    } else if state.winner.as_deref() == Some(COLLECTIVE_ID) {
+        Outcome::Win
+    } else {
+        Outcome::Loss
+    };
+    let usage = meters.0.lock().unwrap().clone();
+    let opponent_usage = meters.1.lock().unwrap().clone();
+
+    Ok(MatchResult {
+        key: key.clone(),
+        seed,
+        outcome,
+        score,
+        opponent_score,
+        rounds: state.round,
+        usage,
+        opponent_usage,
+        emergence_events,
+    })
+}
+
+fn score_of(state: &GameState, player_id: &str) -> i32 {
+    state.players.get(player_id).map(|p| p.score).unwrap_or_default()
+}
+
+/// Results of one cell of the matrix, or of one collective over all of them
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReportRow {
+    pub collective: CollectiveConfig,
+    /// `None` in a collective's summary over every opponent and game type
+    pub opponent: Option<String>,
+    pub game_type: Option<String>,
+    /// Matches finished
+    pub matches: u32,
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+    /// Wins per match
+    pub win_rate: f64,
+    pub average_score: f64,
+    pub average_opponent_score: f64,
+    /// What the collective's models cost, in dollars
+    pub cost_usd: f64,
+    /// What the opponents' models cost, in dollars
+    pub opponent_cost_usd: f64,
+    /// The collective's decisions per second spent deciding
+    pub decisions_per_second: f64,
+    pub emergence_events: u32,
+}
+
+impl ReportRow {
+    fn of(collective: CollectiveConfig, opponent: Option<String>, game_type: Option<String>, results: &[&MatchResult]) -> Self {
+        let count = |outcome| results.iter().filter(|r| r.outcome == outcome).count() as u32;
+        let matches = results.len() as u32;
+        let per_match = |total: f64| if matches == 0 { 0.0 } else { total / matches as f64 };
+        let decisions: u64 = results.iter().map(|r| r.usage.decisions).sum();
+        let decision_ms: u64 = results.iter().map(|r| r.usage.decision_ms).sum();
+        let wins = count(Outcome::Win);
+
+        Self {
+            collective,
+            opponent,
+            game_type,
+            matches,
+            wins,
+            draws: count(Outcome::Draw),
+            losses: count(Outcome::Loss),
+            win_rate: per_match(wins as f64),
+            average_score: per_match(results.iter().map(|r| r.score as f64).sum()),
+            average_opponent_score: per_match(results.iter().map(|r| r.opponent_score as f64).sum()),
+            cost_usd: results.iter().map(|r| r.usage.cost_usd).sum(),
+            opponent_cost_usd: results.iter().map(|r| r.opponent_usage.cost_usd).sum(),
+            decisions_per_second: if decision_ms == 0 { 0.0 } else { decisions as f64 * 1000.0 / decision_ms as f64 },
+            emergence_events: results.iter().map(|r| r.emergence_events).sum(),
+        }
+    }
+
+    fn csv_fields(&self) -> Vec<String> {
+        vec![
+            self.collective.as_str().to_string(),
+            self.opponent.clone().unwrap_or_else(|| "all".to_string()),
+            self.game_type.clone().unwrap_or_else(|| "all".to_string()),
+            self.matches.to_string(),
+            self.wins.to_string(),
+            self.draws.to_string(),
+            self.losses.to_string(),
+            format!("{:.4}", self.win_rate),
+            format!("{:.2}", self.average_score),
+            format!("{:.2}", self.average_opponent_score),
+            format!("{:.6}", self.cost_usd),
+            format!("{:.6}", self.opponent_cost_usd),
+            format!("{:.4}", self.decisions_per_second),
+            self.emergence_events.to_string(),
+        ]
+    }
+}
+
+/// Everything a benchmark run found
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BenchmarkReport {
+    pub generated_at: DateTime<Utc>,
+    pub plan: BenchmarkPlan,
+    pub planned_matches: usize,
+    pub finished_matches: usize,
+    /// A row per cell, in plan order
+    pub cells: Vec<ReportRow>,
+    /// A row per collective, over all its cells
+    pub collectives: Vec<ReportRow>,
+}
+
+impl BenchmarkReport {
+    pub fn of(plan: &BenchmarkPlan, results: &[MatchResult]) -> Self {
+        // Totals are summed in plan order, whatever order matches finished in
+        let by_key: HashMap<&MatchKey, &MatchResult> = results.iter().map(|r| (&r.key, r)).collect();
+        let planned = plan.matches();
+        let ordered: Vec<&MatchResult> = planned.iter().filter_map(|planned| by_key.get(&planned.key).copied()).collect();
+
+        let mut cells = Vec::new();
+        let mut collectives = Vec::new();
+        for &collective in &plan.collectives {
+            let theirs: Vec<&MatchResult> = ordered.iter().filter(|r| r.key.collective == collective).copied().collect();
+            for opponent in &plan.opponents {
+                for game_type in &plan.game_types {
+                    let name = game_type_name(game_type);
+                    let cell: Vec<&MatchResult> = theirs.iter()
+                        .filter(|r| &r.key.opponent == opponent && r.key.game_type == name)
+                        .copied()
+                        .collect();
+                    cells.push(ReportRow::of(collective, Some(opponent.clone()), Some(name), &cell));
+                }
+            }
+            collectives.push(ReportRow::of(collective, None, None, &theirs));
+        }
+
+        Self {
+            generated_at: Utc::now(),
+            plan: plan.clone(),
+            planned_matches: planned.len(),
+            finished_matches: ordered.len(),
+            cells,
+            collectives,
+        }
+    }
+
+    /// Cells, then collectives, one row each
+    pub fn to_csv(&self) -> String {
+        let mut csv = format!("{}\n", CSV_HEADER);
+        for row in self.cells.iter().chain(&self.collectives) {
+            let fields: Vec<String> = row.csv_fields().iter().map(|field| csv_field(field)).collect();
+            csv.push_str(&fields.join(","));
+            csv.push('\n');
+        }
+        csv
+    }
+}
+
+/// Quote a field that would otherwise break the row
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// What one call to [`BenchmarkRunner::run`] did
+#[derive(Debug, Clone)]
+pub struct BenchmarkRun {
+    /// Matches finished by an earlier run
+    pub resumed: usize,
+    /// Matches played this time
+    pub played: usize,
+    pub report: BenchmarkReport,
+}
+
+/// Plays a plan, keeping its state in a run directory
+pub struct BenchmarkRunner {
+    dir: PathBuf,
+    plan: BenchmarkPlan,
+    concurrency: usize,
+    providers: ProviderFactory,
+}
+
+impl BenchmarkRunner {
+    /// Run `plan` in `dir`, asking the models through `providers`
+    pub fn new(dir: impl Into<PathBuf>, plan: BenchmarkPlan, providers: ProviderFactory) -> Self {
+        Self {
+            dir: dir.into(),
+            plan,
+            concurrency: DEFAULT_CONCURRENCY,
+            providers,
+        }
+    }
+
+    /// Play at most `concurrency` matches at once
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Matches finished in the run directory, each once; a line that does
+    /// not parse was cut short and is skipped
+    pub fn finished(&self) -> Result<Vec<MatchResult>, String> {
+        let path = self.dir.join(MATCHES_FILE);
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(format!("Cannot read {}: {}", path.display(), e)),
+        };
+        let mut seen = HashSet::new();
+        Ok(text.lines()
+            .filter_map(|line| serde_json::from_str::<MatchResult>(line).ok())
+            .filter(|result| seen.insert(result.key.clone()))
+            .collect())
+    }
+
+    /// Play every match not finished yet and write the report
+    pub async fn run(&self) -> Result<BenchmarkRun, String> {
+        self.plan.validate()?;
+        fs::create_dir_all(&self.dir)
+            .map_err(|e| format!("Cannot create {}: {}", self.dir.display(), e))?;
+        self.claim_dir()?;
+
+        // Rewrite what survived, so nothing is appended to a cut-short line
+        let mut results = self.finished()?;
+        let resumed = results.len();
+        let mut lines = String::new();
+        for result in &results {
+            lines.push_str(&to_line(result)?);
+        }
+        write_file(&self.dir.join(MATCHES_FILE), &lines)?;
+        let mut log = OpenOptions::new().append(true).open(self.dir.join(MATCHES_FILE))
+            .map_err(|e| format!("Cannot open {}: {}", MATCHES_FILE, e))?;
+
+        let done: HashSet<MatchKey> = results.iter().map(|result| result.key.clone()).collect();
+        let permits = Arc::new(Semaphore::new(self.concurrency));
+        let mut matches = JoinSet::new();
+        for planned in self.plan.matches().into_iter().filter(|planned| !done.contains(&planned.key)) {
+            let permits = permits.clone();
+            let providers = self.providers.clone();
+            let max_rounds = self.plan.max_rounds;
+            matches.spawn(async move {
+                let _permit = permits.acquire_owned().await.map_err(|e| e.to_string())?;
+                tokio::task::spawn_blocking(move || play_match(&planned, max_rounds, &providers))
+                    .await
+                    .map_err(|e| format!("Match panicked: {}", e))?
+            });
+        }
+
+        let mut played = 0;
+        while let Some(joined) = matches.join_next().await {
+            let result = joined.map_err(|e| format!("Match panicked: {}", e))??;
+            log.write_all(to_line(&result)?.as_bytes())
+                .and_then(|_| log.flush())
+                .map_err(|e| format!("Cannot record match: {}", e))?;
+            results.push(result);
+            played += 1;
+        }
+
+        let report = BenchmarkReport::of(&self.plan, &results);
+        let json = serde_json::to_string_pretty(&report).map_err(|e| e.to_string())?;
+        write_file(&self.dir.join(REPORT_JSON_FILE), &json)?;
+        write_file(&self.dir.join(REPORT_CSV_FILE), &report.to_csv())?;
+        Ok(BenchmarkRun { resumed, played, report })
+    }
+
+    /// Record the plan in the run directory, or check it is the plan
+    /// already recorded there
+    fn claim_dir(&self) -> Result<(), String> {
+        let path = self.dir.join(PLAN_FILE);
+        let plan = serde_json::to_value(&self.plan).map_err(|e| e.to_string())?;
+        match fs::read_to_string(&path) {
+            Ok(text) => {
+                let recorded: serde_json::Value = serde_json::from_str(&text)
+                    .map_err(|e| format!("Cannot parse {}: {}", path.display(), e))?;
+                if recorded != plan {
+                    return Err(format!("{} holds a run of a different plan", self.dir.display()));
+                }
+                Ok(())
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                let text = serde_json::to_string_pretty(&plan).map_err(|e| e.to_string())?;
+                write_file(&path, &text)
+            }
+            Err(e) => Err(format!("Cannot read {}: {}", path.display(), e)),
+        }
+    }
+}
+
+fn to_line(result: &MatchResult) -> Result<String, String> {
+    serde_json::to_string(result).map(|line| line + "\n").map_err(|e| e.to_string())
+}
+
+fn write_file(path: &Path, contents: &str) -> Result<(), String> {
+    fs::write(path, contents).map_err(|e| format!("Cannot write {}: {}", path.display(), e))
+}
+
+/// Ask every model of `plan` for a short answer
+async fn check_models(plan: &BenchmarkPlan) -> Result<(), String> {
+    let mut models: Vec<&str> = plan.collectives.iter()
+        .flat_map(|collective| collective.models())
+        .chain(plan.opponents.iter().map(String::as_str))
+        .collect();
+    models.sort_unstable();
+    models.dedup();
+    for model in models {
+        ai_provider::from_env(model).complete("Reply with OK.").await
+            .map_err(|e| format!("{} cannot answer: {}", model, e))?;
+    }
+    Ok(())
+}
+
+/// `benchmark <plan.json> <run-dir> [--concurrency N] [--live]`: run or
+/// resume a plan with [`MockProvider`]s, or the models themselves with
+/// `--live`, and print the report as CSV
+pub async fn run_cli(args: &[String]) -> Result<(), String> {
+    const USAGE: &str = "usage: ai-genius-game benchmark <plan.json> <run-dir> [--concurrency N] [--live]";
+    let (plan_path, dir) = match args {
+        [plan, dir, ..] => (plan, dir),
+        _ => return Err(USAGE.to_string()),
+    };
+    let mut concurrency = DEFAULT_CONCURRENCY;
+    let mut providers = MockProvider::factory();
+    let mut flags = args[2..].iter();
+    while let Some(flag) = flags.next() {
+        match flag.as_str() {
+            "--concurrency" => {
+                concurrency = flags.next().and_then(|n| n.parse().ok()).ok_or_else(|| USAGE.to_string())?;
+            }
+            "--live" => providers = ModelDecisionProvider::factory(ai_provider::from_env),
+            _ => return Err(USAGE.to_string()),
+        }
+    }
+    let live = args[2..].iter().any(|flag| flag == "--live");
+    let text = fs::read_to_string(plan_path).map_err(|e| format!("Cannot read {}: {}", plan_path, e))?;
+    let plan: BenchmarkPlan = serde_json::from_str(&text).map_err(|e| format!("Invalid plan: {}", e))?;
+    if live {
+        check_models(&plan).await?;
+    }
+
+    let run = BenchmarkRunner::new(dir, plan, providers).with_concurrency(concurrency).run().await?;
+    eprintln!(
+        "{} of {} matches finished ({} resumed, {} played)",
+        run.report.finished_matches, run.report.planned_matches, run.resumed, run.played,
+    );
+    print!("{}", run.report.to_csv());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collective::{model_profile, DecisionProvider, ProviderDecision, MOCK_COMPLETION_TOKENS, MOCK_PROMPT_TOKENS};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn plan() -> BenchmarkPlan {
+        BenchmarkPlan {
+            collectives: vec![CollectiveConfig::OpusOrchestra, CollectiveConfig::EmergenceEngine],
+            opponents: vec!["claude-opus-4".to_string()],
+            game_types: vec![GameType::MinorityGame, GameType::ConsciousnessEmergence],
+            matches_per_cell: 3,
+            max_rounds: 4,
+            seed: 7,
+        }
+    }
+
+    fn temp_dir() -> PathBuf {
+        std::env::temp_dir().join(format!("genius-benchmark-{}", uuid::Uuid::new_v4()))
+    }
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    fn cell<'a>(report: &'a BenchmarkReport, collective: CollectiveConfig, game_type: &str) -> &'a ReportRow {
+        report.cells.iter()
+            .find(|row| row.collective == collective && row.game_type.as_deref() == Some(game_type))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_report_math() {
+        let dir = temp_dir();
+        let run = BenchmarkRunner::new(&dir, plan(), MockProvider::factory()).with_concurrency(3).run().await.unwrap();
+        let results = BenchmarkRunner::new(&dir, plan(), MockProvider::factory()).finished().unwrap();
+        let report = &run.report;
+        assert_eq!((run.resumed, run.played), (0, 12));
+        assert_eq!((report.planned_matches, report.finished_matches, results.len()), (12, 12, 12));
+        assert_eq!(report.cells.len(), 4);
+
+        for row in &report.cells {
+            let cell: Vec<&MatchResult> = results.iter()
+                .filter(|r| r.key.collective == row.collective && Some(&r.key.game_type) == row.game_type.as_ref())
+                .collect();
+            let wins = cell.iter().filter(|r| r.outcome == Outcome::Win).count() as u32;
+            assert_eq!((row.matches, row.wins), (3, wins));
+            assert_eq!(row.wins + row.draws + row.losses, 3);
+            assert!(close(row.win_rate, wins as f64 / 3.0));
+            assert!(close(row.average_score, cell.iter().map(|r| r.score as f64).sum::<f64>() / 3.0));
+            assert!(close(row.opponent_cost_usd, cell.iter().map(|r| r.opponent_usage.cost_usd).sum()));
+            assert_eq!(row.emergence_events, cell.iter().map(|r| r.emergence_events).sum::<u32>());
+        }
+
+        // Every round of the minority game every agent has a side to pick:
+        // the orchestra asks all six Opus agents at once, the engine one
+        // Haiku agent in turn
+        let per_call = |model| model_profile(model).cost(MOCK_PROMPT_TOKENS, MOCK_COMPLETION_TOKENS);
+        let orchestra = cell(report, CollectiveConfig::OpusOrchestra, "MinorityGame");
+        assert!(close(orchestra.cost_usd, 3.0 * 4.0 * 6.0 * per_call("claude-opus-4")));
+        assert!(close(orchestra.decisions_per_second, 1000.0 / model_profile("claude-opus-4").latency_ms as f64));
+        let engine = cell(report, CollectiveConfig::EmergenceEngine, "MinorityGame");
+        assert!(close(engine.cost_usd, 3.0 * 4.0 * per_call("claude-haiku-3")));
+        assert!(close(engine.decisions_per_second, 1000.0 / model_profile("claude-haiku-3").latency_ms as f64));
+        assert!(close(engine.opponent_cost_usd, 3.0 * 4.0 * per_call("claude-opus-4")));
+
+        // A collective's summary adds up its cells
+        for summary in &report.collectives {
+            let cells: Vec<&ReportRow> = report.cells.iter().filter(|row| row.collective == summary.collective).collect();
+            assert_eq!(summary.matches, 6);
+            assert_eq!(summary.wins, cells.iter().map(|row| row.wins).sum::<u32>());
+            assert!(close(summary.cost_usd, cells.iter().map(|row| row.cost_usd).sum()));
+            assert!(close(summary.average_score, cells.iter().map(|row| row.average_score).sum::<f64>() / 2.0));
+        }
+
+        // Both exports are on disk
+        let csv = fs::read_to_string(dir.join(REPORT_CSV_FILE)).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], CSV_HEADER);
+        assert_eq!(lines.len(), 1 + 4 + 2);
+        assert!(lines[1].starts_with("opus_orchestra,claude-opus-4,MinorityGame,3,"), "{}", lines[1]);
+        assert!(lines[6].starts_with("emergence_engine,all,all,6,"), "{}", lines[6]);
+        let saved: BenchmarkReport = serde_json::from_str(&fs::read_to_string(dir.join(REPORT_JSON_FILE)).unwrap()).unwrap();
+        assert_eq!(saved.cells, report.cells);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_interrupted_run_resumes() {
+        let complete = temp_dir();
+        let expected = BenchmarkRunner::new(&complete, plan(), MockProvider::factory()).run().await.unwrap().report;
+
+        // Interrupted after three matches, halfway through recording a fourth
+        let interrupted = temp_dir();
+        fs::create_dir_all(&interrupted).unwrap();
+        fs::copy(complete.join(PLAN_FILE), interrupted.join(PLAN_FILE)).unwrap();
+        let recorded = fs::read_to_string(complete.join(MATCHES_FILE)).unwrap();
+        let lines: Vec<&str> = recorded.lines().collect();
+        let partial = format!("{}\n{}", lines[..3].join("\n"), &lines[3][..lines[3].len() / 2]);
+        fs::write(interrupted.join(MATCHES_FILE), partial).unwrap();
+
+        let runner = BenchmarkRunner::new(&interrupted, plan(), MockProvider::factory()).with_concurrency(2);
+        assert_eq!(runner.finished().unwrap().len(), 3);
+        let run = runner.run().await.unwrap();
+        assert_eq!((run.resumed, run.played), (3, 9));
+        assert_eq!(run.report.cells, expected.cells);
+        assert_eq!(run.report.collectives, expected.collectives);
+        assert_eq!(runner.finished().unwrap().len(), 12);
+
+        // Nothing is left to play
+        let again = runner.run().await.unwrap();
+        assert_eq!((again.resumed, again.played), (12, 0));
+
+        // A run directory belongs to one plan
+        let other = BenchmarkPlan { seed: 8, ..plan() };
+        assert!(BenchmarkRunner::new(&interrupted, other, MockProvider::factory()).run().await.is_err());
+
+        fs::remove_dir_all(&complete).unwrap();
+        fs::remove_dir_all(&interrupted).unwrap();
+    }
+
+    /// Counts the matches deciding at once
+    struct Counted {
+        inner: MockProvider,
+        active: Arc<AtomicUsize>,
+        most: Arc<AtomicUsize>,
+    }
+
+    impl DecisionProvider for Counted {
+        fn model(&self) -> &str {
+            self.inner.model()
+        }
+
+        fn decide(&mut self, state: &GameState, player_id: &str) -> ProviderDecision {
+            let now = self.active.fetch_add(1, Ordering::SeqCst) + 1;
+            self.most.fetch_max(now, Ordering::SeqCst);
+            std::thread::sleep(std::time::Duration::from_millis(2));
+            self.active.fetch_sub(1, Ordering::SeqCst);
+            self.inner.decide(state, player_id)
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_concurrency_is_bounded() {
+        let active = Arc::new(AtomicUsize::new(0));
+        let most = Arc::new(AtomicUsize::new(0));
+        let (a, m) = (active.clone(), most.clone());
+        let providers: ProviderFactory = Arc::new(move |model, seed| {
+            Box::new(Counted { inner: MockProvider::new(model, seed), active: a.clone(), most: m.clone() })
+                as Box<dyn DecisionProvider>
+        });
+        let plan = BenchmarkPlan {
+            collectives: vec![CollectiveConfig::EmergenceEngine],
+            game_types: vec![GameType::MinorityGame],
+            matches_per_cell: 8,
+            ..plan()
+        };
+
+        let dir = temp_dir();
+        let run = BenchmarkRunner::new(&dir, plan, providers).with_concurrency(2).run().await.unwrap();
+        assert_eq!(run.played, 8);
+        assert!(most.load(Ordering::SeqCst) <= 2);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_matches_are_seeded_by_place() {
+        let matches = plan().matches();
+        assert_eq!(matches.len(), 12);
+        let seeds: HashSet<u64> = matches.iter().map(|planned| planned.seed).collect();
+        assert_eq!(seeds.len(), 12);
+        let providers = MockProvider::factory();
+        assert_eq!(play_match(&matches[4], 4, &providers).unwrap(), play_match(&matches[4], 4, &providers).unwrap());
+        assert!(BenchmarkPlan { matches_per_cell: 0, ..plan() }.validate().is_err());
+    }
+}
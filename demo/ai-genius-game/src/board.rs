@@ -0,0 +1,132 @@
+//! Sparse game board
+//!
+//! Only occupied cells are stored. Cells are kept in row-major order so that
+//! anything folding over the board (consciousness in particular) visits them
+//! in the same order the old dense `Vec<Vec<Cell>>` did.
+//!
+//! Boards serialize as `{"size": 19, "cells": [{"x", "y", ...cell}]}`. The
+//! old dense format (an array of rows) is still accepted when reading.
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::BTreeMap;
+
+use crate::Cell;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Board {
+    size: usize,
+    /// Occupied cells keyed by (y, x) for row-major iteration
+    cells: BTreeMap<(usize, usize), Cell>,
+}
+
+impl Board {
+    /// An empty `size` x `size` board
+    pub fn new(size: usize) -> Self {
+        Self { size, cells: BTreeMap::new() }
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn in_bounds(&self, x: usize, y: usize) -> bool {
+        x < self.size && y < self.size
+    }
+
+    /// The cell at (x, y), if occupied
+    pub fn get(&self, x: usize, y: usize) -> Option<&Cell> {
+        self.cells.get(&(y, x))
+    }
+
+    pub fn get_mut(&mut self, x: usize, y: usize) -> Option<&mut Cell> {
+        self.cells.get_mut(&(y, x))
+    }
+
+    /// Owner of the cell at (x, y), if occupied
+    pub fn owner(&self, x: usize, y: usize) -> Option<&str> {
+        self.get(x, y).and_then(|cell| cell.owner.as_deref())
+    }
+
+    pub fn is_occupied(&self, x: usize, y: usize) -> bool {
+        self.owner(x, y).is_some()
+    }
+
+    /// Store a cell; unowned empty cells are dropped to keep the board sparse
+    pub fn set(&mut self, x: usize, y: usize, cell: Cell) {
+        if is_empty(&cell) {
+            self.cells.remove(&(y, x));
+        } else {
+            self.cells.insert((y, x), cell);
+        }
+    }
+
+    /// Stored cells as ((x, y), cell), in row-major order
+    pub fn iter(&self) -> impl Iterator<Item = ((usize, usize), &Cell)> {
+        self.cells.iter().map(|(&(y, x), cell)| ((x, y), cell))
+    }
+}
+
+fn is_empty(cell: &Cell) -> bool {
+    cell.owner.is_none() && cell.neuron_strength == 0.0 && cell.connections.is_empty()
+}
+
+/// A stored cell with its position, as serialized
+#[derive(Serialize, Deserialize)]
+struct PositionedCell {
+    x: usize,
+    y: usize,
+    #[serde(flatten)]
+    cell: Cell,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SparseBoard {
+    size: usize,
+    cells: Vec<PositionedCell>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum BoardRepr {
+    Sparse(SparseBoard),
+    Dense(Vec<Vec<Cell>>),
+}
+
+impl Serialize for Board {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        SparseBoard {
+            size: self.size,
+            cells: self.iter()
+                .map(|((x, y), cell)| PositionedCell { x, y, cell: cell.clone() })
+                .collect(),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Board {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let board = match BoardRepr::deserialize(deserializer)? {
+            BoardRepr::Sparse(sparse) => {
+                let mut board = Board::new(sparse.size);
+                for PositionedCell { x, y, cell } in sparse.cells {
+                    if !board.in_bounds(x, y) {
+                        return Err(serde::de::Error::custom(format!("cell ({}, {}) is off the board", x, y)));
+                    }
+                    board.set(x, y, cell);
+                }
+                board
+            }
+            BoardRepr::Dense(rows) => {
+                let mut board = Board::new(rows.len());
+                for (y, row) in rows.into_iter().enumerate() {
+                    for (x, cell) in row.into_iter().enumerate() {
+                        board.set(x, y, cell);
+                    }
+                }
+                board
+            }
+        };
+        Ok(board)
+    }
+}
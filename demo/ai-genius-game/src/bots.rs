@@ -0,0 +1,472 @@
+//! Non-LLM baseline bots
+//!
+//! Bots play through the same [`rules`](crate::rules) as human and AI players,
+//! so their decisions are validated and recorded identically. Every bot draws
+//! from a seeded RNG, which makes benchmark runs reproducible.
+//!
+//! In the oracle paradox every bot commits to and reveals its predictions
+//! the same way (see [`OracleBot`]); the strategies only pick sides.
+
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+use crate::maze::Direction;
+use crate::oracle::{self, OraclePhase};
+use crate::{rules, GameAction, GameState, GameType};
+
+/// Default thinking time for the Monte-Carlo bot
+pub const DEFAULT_BUDGET_MS: u64 = 50;
+
+/// Rollouts per decision for the Monte-Carlo bot, whatever the budget
+const MAX_ROLLOUTS: usize = 64;
+
+/// Rounds simulated per rollout
+const ROLLOUT_DEPTH: usize = 3;
+
+/// Candidate actions the Monte-Carlo bot evaluates per decision
+const MAX_CANDIDATES: usize = 8;
+
+/// Attempts at drawing a random valid action before giving up
+const RANDOM_ATTEMPTS: usize = 64;
+
+/// Mixed into a bot's seed for the RNG its oracle paradox predictions draw from
+const ORACLE_STREAM: u64 = 0x4f52_4143_4c45_4254;
+
+/// Built-in bot strategies
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BotKind {
+    Random,
+    Greedy,
+    TitForTat,
+    MonteCarlo,
+}
+
+impl BotKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BotKind::Random => "random",
+            BotKind::Greedy => "greedy",
+            BotKind::TitForTat => "tit_for_tat",
+            BotKind::MonteCarlo => "monte_carlo",
+        }
+    }
+}
+
+/// A player driven by a fixed strategy
+pub trait Bot: Send {
+    /// Called with the game state at the start of every round
+    fn observe(&mut self, _state: &GameState) {}
+
+    /// Pick an action for `player_id`, or `None` to pass this round
+    fn choose_action(&mut self, state: &GameState, player_id: &str) -> Option<GameAction>;
+
+    /// Called once the match is over
+    fn finish(&mut self) {}
+}
+
+/// Create a bot of the given kind
+pub fn create_bot(kind: BotKind, seed: u64, budget_ms: Option<u64>) -> Box<dyn Bot> {
+    let strategy: Box<dyn Bot> = match kind {
+        BotKind::Random => Box::new(RandomBot::new(seed)),
+        BotKind::Greedy => Box::new(GreedyBot::new(seed)),
+        BotKind::TitForTat => Box::new(TitForTatBot::new(seed)),
+        BotKind::MonteCarlo => Box::new(MonteCarloBot::new(
+            seed,
+            Duration::from_millis(budget_ms.unwrap_or(DEFAULT_BUDGET_MS)),
+        )),
+    };
+    Box::new(OracleBot::new(strategy, seed))
+}
+
+/// Draw a valid action uniformly-ish without enumerating the whole board
+pub fn random_action(state: &GameState, player_id: &str, rng: &mut StdRng) -> Option<GameAction> {
+    for _ in 0..RANDOM_ATTEMPTS {
+        let action = match state.game_type {
+            GameType::MinorityGame | GameType::OracleParadox => {
+                GameAction::ChooseSide { side: rng.gen_range(0..rules::MINORITY_SIDES) }
+            }
+            GameType::SemanticShapeshifter => {
+                let words = state.rules.shapeshifter().vocabulary().words();
+                GameAction::SubmitWord { word: words.choose(rng)?.clone() }
+            }
+            GameType::CollectiveMaze => GameAction::Move { direction: *Direction::ALL.choose(rng)? },
+            _ => {
+                let size = state.board.size();
+                let (x, y) = (rng.gen_range(0..size), rng.gen_range(0..size));
+                match state.board.owner(x, y) {
+                    None => GameAction::PlaceNeuron { x, y },
+                    Some(owner) if owner == player_id => {
+                        let to = (rng.gen_range(0..size), rng.gen_range(0..size));
+                        GameAction::StrengthenConnection { from: (x, y), to }
+                    }
+                    Some(_) => continue,
+                }
+            }
+        };
+        if rules::validate_action(state, player_id, &action).is_ok() {
+            return Some(action);
+        }
+    }
+
+    // Crowded boards: fall back to an exhaustive search
+    rules::legal_actions(state, player_id).choose(rng).cloned()
+}
+
+/// Makes a strategy's oracle paradox predictions and keeps them until the
+/// reveal phase; outside the commit and reveal phases the strategy plays
+///
+/// The bot predicts side 0 will be chosen as often as in the last round,
+/// or picks a prediction at random before there is one.
+pub struct OracleBot {
+    strategy: Box<dyn Bot>,
+    rng: StdRng,
+    /// Round, prediction and nonce of the last commitment
+    committed: Option<(u32, u8, String)>,
+}
+
+impl OracleBot {
+    pub fn new(strategy: Box<dyn Bot>, seed: u64) -> Self {
+        Self { strategy, rng: StdRng::seed_from_u64(seed ^ ORACLE_STREAM), committed: None }
+    }
+}
+
+impl Bot for OracleBot {
+    fn observe(&mut self, state: &GameState) {
+        self.strategy.observe(state);
+    }
+
+    fn choose_action(&mut self, state: &GameState, player_id: &str) -> Option<GameAction> {
+        if !matches!(state.game_type, GameType::OracleParadox) {
+            return self.strategy.choose_action(state, player_id);
+        }
+        match state.oracle.phase {
+            OraclePhase::Commit => {
+                let prediction = match state.oracle.rounds.last().and_then(|round| round.share) {
+                    Some(share) => share.round() as u8,
+                    None => self.rng.gen_range(0..=100),
+                };
+                let nonce = format!("{:016x}", self.rng.gen::<u64>());
+                let commitment = oracle::commitment(prediction, &nonce);
+                self.committed = Some((state.round, prediction, nonce));
+                Some(GameAction::Commit { commitment })
+            }
+            OraclePhase::Act => self.strategy.choose_action(state, player_id),
+            OraclePhase::Reveal => match &self.committed {
+                Some((round, prediction, nonce)) if *round == state.round => {
+                    Some(GameAction::Reveal { prediction: *prediction, nonce: nonce.clone() })
+                }
+                _ => None,
+            },
+        }
+    }
+
+    fn finish(&mut self) {
+        self.strategy.finish();
+    }
+}
+
+/// Plays a random valid action every round
+pub struct RandomBot {
+    rng: StdRng,
+}
+
+impl RandomBot {
+    pub fn new(seed: u64) -> Self {
+        Self { rng: StdRng::seed_from_u64(seed) }
+    }
+}
+
+impl Bot for RandomBot {
+    fn choose_action(&mut self, state: &GameState, player_id: &str) -> Option<GameAction> {
+        random_action(state, player_id, &mut self.rng)
+    }
+}
+
+/// Maximizes immediate score, breaking ties by resulting consciousness
+pub struct GreedyBot {
+    rng: StdRng,
+}
+
+impl GreedyBot {
+    pub fn new(seed: u64) -> Self {
+        Self { rng: StdRng::seed_from_u64(seed) }
+    }
+}
+
+impl Bot for GreedyBot {
+    fn choose_action(&mut self, state: &GameState, player_id: &str) -> Option<GameAction> {
+        let totals = rules::board_totals(state);
+        let mut best: Vec<GameAction> = Vec::new();
+        let mut best_key = (i32::MIN, f32::MIN);
+
+        for action in rules::legal_actions(state, player_id) {
+            let Some(preview) = rules::preview_action(state, totals, player_id, &action) else {
+                continue;
+            };
+            let key = (preview.score_delta, preview.consciousness);
+            if key > best_key {
+                best_key = key;
+                best.clear();
+            }
+            if key == best_key {
+                best.push(action);
+            }
+        }
+
+        best.choose(&mut self.rng).cloned()
+    }
+}
+
+/// Minority game strategy that follows last round's winning side, which it
+/// also plays in the oracle paradox
+///
+/// In other games it falls back to random play.
+pub struct TitForTatBot {
+    rng: StdRng,
+    last_minority: Option<u8>,
+}
+
+impl TitForTatBot {
+    pub fn new(seed: u64) -> Self {
+        Self { rng: StdRng::seed_from_u64(seed), last_minority: None }
+    }
+}
+
+impl Bot for TitForTatBot {
+    fn observe(&mut self, state: &GameState) {
+        self.last_minority = match state.game_type {
+            GameType::OracleParadox => state.oracle.rounds.last().and_then(|round| round.minority),
+            _ => state.minority.history.last().copied(),
+        };
+    }
+
+    fn choose_action(&mut self, state: &GameState, player_id: &str) -> Option<GameAction> {
+        if let (GameType::MinorityGame | GameType::OracleParadox, Some(side)) = (&state.game_type, self.last_minority) {
+            let action = GameAction::ChooseSide { side };
+            if rules::validate_action(state, player_id, &action).is_ok() {
+                return Some(action);
+            }
+        }
+        random_action(state, player_id, &mut self.rng)
+    }
+}
+
+/// Evaluates candidate actions by random rollouts within a time budget
+///
+/// The rollout count is also capped, so with a generous budget the bot is
+/// fully deterministic for a given seed.
+pub struct MonteCarloBot {
+    rng: StdRng,
+    budget: Duration,
+}
+
+impl MonteCarloBot {
+    pub fn new(seed: u64, budget: Duration) -> Self {
+        Self { rng: StdRng::seed_from_u64(seed), budget }
+    }
+
+    /// Play `action`, then `ROLLOUT_DEPTH` random rounds, and return our score
+    fn rollout(&mut self, state: &GameState, player_id: &str, action: &GameAction) -> i32 {
+        let mut sim = snapshot(state);
+        let mut player_ids: Vec<String> = sim.players.keys().cloned().collect();
+        player_ids.sort();
+
+        if rules::apply_action(&mut sim, player_id, action.clone()).is_err() {
+            return i32::MIN;
+        }
+        for depth in 0..ROLLOUT_DEPTH {
+            for id in &player_ids {
+                if depth == 0 && id == player_id {
+                    continue;
+                }
+                if let Some(a) = random_action(&sim, id, &mut self.rng) {
+                    let _ = rules::apply_action(&mut sim, id, a);
+                }
+            }
+            rules::end_round(&mut sim);
+            sim.decisions.clear();
+            sim.events.clear();
+        }
+
+        sim.players.get(player_id).map(|p| p.score).unwrap_or_default()
+    }
+}
+
+impl Bot for MonteCarloBot {
+    fn choose_action(&mut self, state: &GameState, player_id: &str) -> Option<GameAction> {
+        let mut candidates = rules::legal_actions(state, player_id);
+        if candidates.is_empty() {
+            return None;
+        }
+        candidates.shuffle(&mut self.rng);
+        candidates.truncate(MAX_CANDIDATES);
+
+        let deadline = Instant::now() + self.budget;
+        let mut totals = vec![0i64; candidates.len()];
+        let mut runs = vec![0u32; candidates.len()];
+
+        for i in 0..MAX_ROLLOUTS {
+            if i >= candidates.len() && Instant::now() >= deadline {
+                break;
+            }
+            let index = i % candidates.len();
+            totals[index] += self.rollout(state, player_id, &candidates[index]) as i64;
+            runs[index] += 1;
+        }
+
+        (0..candidates.len())
+            .filter(|&i| runs[i] > 0)
+            .max_by(|&a, &b| {
+                let mean = |i: usize| totals[i] as f64 / runs[i] as f64;
+                mean(a).total_cmp(&mean(b)).then_with(|| b.cmp(&a))
+            })
+            .map(|i| candidates[i].clone())
+    }
+}
+
+/// Copy of the game without its event and decision logs, for simulation
+fn snapshot(state: &GameState) -> GameState {
+    GameState {
+        id: state.id.clone(),
+        game_type: state.game_type.clone(),
+        status: state.status.clone(),
+        round: state.round,
+        max_rounds: state.max_rounds,
+        players: state.players.clone(),
+        board: state.board.clone(),
+        consciousness_level: state.consciousness_level,
+        events: Vec::new(),
+        winner: state.winner.clone(),
+        decisions: Vec::new(),
+        minority: state.minority.clone(),
+        shapeshifter: state.shapeshifter.clone(),
+        oracle: state.oracle.clone(),
+        maze: state.maze.clone(),
+        seed: state.seed,
+        rules: state.rules.simulation(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maze::MazeState;
+    use crate::{new_game, GameStatus, Player, PlayerType};
+
+    /// Rounds of most games, a third as many oracle paradox rounds
+    const PHASES: u32 = 1000;
+
+    fn game_with_bots(game_type: GameType, kind: BotKind, count: u32, seed: u64) -> (GameState, Vec<(String, Box<dyn Bot>)>) {
+        let mut state = new_game(game_type, 20, Some(seed));
+        state.status = GameStatus::Running;
+        let mut bots = Vec::new();
+        for i in 0..count {
+            let id = format!("bot-{}-{}", kind.as_str(), i);
+            state.players.insert(id.clone(), Player {
+                id: id.clone(),
+                name: id.clone(),
+                player_type: PlayerType::Bot { strategy: kind },
+                score: 0,
+                neurons_placed: 0,
+                color: "#ffffff".to_string(),
+            });
+            bots.push((id, create_bot(kind, seed.wrapping_add(i as u64), Some(1_000))));
+        }
+        place_avatars(&mut state);
+        (state, bots)
+    }
+
+    /// Lay out the maze and its avatars, which `start_game` would do
+    fn place_avatars(state: &mut GameState) {
+        if let GameType::CollectiveMaze = state.game_type {
+            state.maze = MazeState::generate(state.rules.seed());
+            for player_id in state.players.keys() {
+                state.maze.add_avatar(player_id);
+            }
+        }
+    }
+
+    /// Play `PHASES` phases, restarting finished games, and return every decision
+    fn simulate(game_type: GameType, kind: BotKind, seed: u64) -> Vec<(u32, String, String)> {
+        let (mut state, mut bots) = game_with_bots(game_type, kind, 3, seed);
+        let mut decisions = Vec::new();
+
+        for _ in 0..PHASES {
+            if state.status == GameStatus::Finished {
+                let players = state.players.clone();
+                state = new_game(state.game_type.clone(), 20, Some(seed));
+                state.status = GameStatus::Running;
+                state.players = players.into_iter()
+                    .map(|(id, p)| (id, Player { score: 0, neurons_placed: 0, ..p }))
+                    .collect();
+                place_avatars(&mut state);
+            }
+            for (_, bot) in bots.iter_mut() {
+                bot.observe(&state);
+            }
+            for (id, bot) in bots.iter_mut() {
+                if state.status != GameStatus::Running {
+                    break;
+                }
+                if let Some(action) = bot.choose_action(&state, id) {
+                    rules::apply_action(&mut state, id, action)
+                        .unwrap_or_else(|e| panic!("{} emitted an invalid action: {}", kind.as_str(), e));
+                }
+            }
+            decisions.extend(state.decisions.drain(..).map(|d| {
+                (d.round, d.player_id, serde_json::to_string(&d.action).unwrap())
+            }));
+            rules::end_phase(&mut state);
+        }
+
+        decisions
+    }
+
+    fn all_kinds() -> [BotKind; 4] {
+        [BotKind::Random, BotKind::Greedy, BotKind::TitForTat, BotKind::MonteCarlo]
+    }
+
+    #[test]
+    fn test_bots_never_emit_invalid_actions() {
+        for kind in all_kinds() {
+            for game_type in [
+                GameType::ConsciousnessEmergence,
+                GameType::MinorityGame,
+                GameType::SemanticShapeshifter,
+                GameType::OracleParadox,
+                GameType::CollectiveMaze,
+            ] {
+                let decisions = simulate(game_type, kind, 7);
+                assert!(!decisions.is_empty(), "{} made no decisions", kind.as_str());
+            }
+        }
+    }
+
+    #[test]
+    fn test_same_seed_gives_same_decisions() {
+        for kind in all_kinds() {
+            assert_eq!(
+                simulate(GameType::ConsciousnessEmergence, kind, 42),
+                simulate(GameType::ConsciousnessEmergence, kind, 42),
+            );
+            assert_eq!(
+                simulate(GameType::MinorityGame, kind, 42),
+                simulate(GameType::MinorityGame, kind, 42),
+            );
+            assert_eq!(
+                simulate(GameType::SemanticShapeshifter, kind, 42),
+                simulate(GameType::SemanticShapeshifter, kind, 42),
+            );
+            assert_eq!(
+                simulate(GameType::OracleParadox, kind, 42),
+                simulate(GameType::OracleParadox, kind, 42),
+            );
+            assert_eq!(
+                simulate(GameType::CollectiveMaze, kind, 42),
+                simulate(GameType::CollectiveMaze, kind, 42),
+            );
+        }
+    }
+}
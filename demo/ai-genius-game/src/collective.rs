@@ -0,0 +1,410 @@
+//! HAL9 collectives and the models they consult
+//!
+//! A [`CollectiveConfig`] preset names the models a collective is made of
+//! and how it coordinates them. Every decision, the collective asks its
+//! agents' [`DecisionProvider`]s for an action and either plays the one
+//! most agents proposed or lets a single agent decide. A single AI is a
+//! collective of one.
+//!
+//! [`MockProvider`] stands in for a model: it plays a baseline strategy
+//! (see [`bots`](crate::bots)) and reports the tokens and latency the model
+//! would have taken, priced by [`model_profile`].
+//! [`ModelDecisionProvider`](crate::ai_provider::ModelDecisionProvider)
+//! asks real models through their APIs.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+use crate::ai_provider::TokenUsage;
+use crate::bots::{self, Bot, BotKind};
+use crate::{GameAction, GameState};
+
+/// Prompt tokens the mock provider reports per decision
+pub const MOCK_PROMPT_TOKENS: u32 = 600;
+
+/// Completion tokens the mock provider reports per decision
+pub const MOCK_COMPLETION_TOKENS: u32 = 50;
+
+/// HAL9 collective presets
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum CollectiveConfig {
+    /// 6x Claude Opus with voting
+    OpusOrchestra,
+    /// 32x small models with swarm voting
+    LightweightLegion,
+    /// Mixed SOTA models with voting
+    HybridCouncil,
+    /// Pure emergence: one agent in turn decides, no coordination
+    EmergenceEngine,
+}
+
+/// How a collective turns its agents' proposals into one action
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Coordination {
+    /// Every agent proposes; the action proposed most wins, ties to the
+    /// earliest proposer
+    Vote,
+    /// The agents take turns deciding alone
+    Rotate,
+}
+
+impl CollectiveConfig {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CollectiveConfig::OpusOrchestra => "opus_orchestra",
+            CollectiveConfig::LightweightLegion => "lightweight_legion",
+            CollectiveConfig::HybridCouncil => "hybrid_council",
+            CollectiveConfig::EmergenceEngine => "emergence_engine",
+        }
+    }
+
+    /// Model of every agent, in order
+    pub fn models(&self) -> Vec<&'static str> {
+        match self {
+            CollectiveConfig::OpusOrchestra => vec!["claude-opus-4"; 6],
+            CollectiveConfig::LightweightLegion => vec!["llama-3-8b"; 32],
+            CollectiveConfig::HybridCouncil => vec!["claude-opus-4", "gpt-4o", "gemini-2.5-pro"],
+            CollectiveConfig::EmergenceEngine => vec!["claude-haiku-3"; 8],
+        }
+    }
+
+    pub fn coordination(&self) -> Coordination {
+        match self {
+            CollectiveConfig::EmergenceEngine => Coordination::Rotate,
+            _ => Coordination::Vote,
+        }
+    }
+}
+
+/// Pricing and mock behaviour of a model
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ModelProfile {
+    /// Dollars per 1k prompt tokens
+    pub prompt_per_1k: f64,
+    /// Dollars per 1k completion tokens
+    pub completion_per_1k: f64,
+    /// Time the mock provider reports per decision
+    pub latency_ms: u64,
+    /// Strategy the mock provider plays
+    pub strategy: BotKind,
+}
+
+impl ModelProfile {
+    /// Dollars for one call
+    pub fn cost(&self, prompt_tokens: u32, completion_tokens: u32) -> f64 {
+        prompt_tokens as f64 / 1000.0 * self.prompt_per_1k
+            + completion_tokens as f64 / 1000.0 * self.completion_per_1k
+    }
+}
+
+/// Profile of a model; unknown models are priced like Sonnet
+pub fn model_profile(model: &str) -> ModelProfile {
+    let (prompt_per_1k, completion_per_1k, latency_ms, strategy) = match model {
+        "claude-opus-4" => (0.015, 0.075, 4000, BotKind::Greedy),
+        "claude-sonnet-4" => (0.003, 0.015, 2000, BotKind::Greedy),
+        "claude-haiku-3" => (0.00025, 0.00125, 600, BotKind::TitForTat),
+        "gpt-4o" => (0.0025, 0.01, 1800, BotKind::Greedy),
+        "gemini-2.5-pro" => (0.00125, 0.01, 2500, BotKind::Greedy),
+        "llama-3-8b" => (0.00005, 0.00008, 300, BotKind::Random),
+        _ => (0.003, 0.015, 2000, BotKind::Greedy),
+    };
+    ModelProfile { prompt_per_1k, completion_per_1k, latency_ms, strategy }
+}
+
+/// What a model proposed and what asking it took
+#[derive(Debug, Clone)]
+pub struct ProviderDecision {
+    /// `None` if the model passed or answered with nothing playable
+    pub action: Option<GameAction>,
+    pub prompt_tokens: u32,
+    pub completion_tokens: u32,
+    pub latency_ms: u64,
+}
+
+/// A model an agent consults for each decision
+pub trait DecisionProvider: Send {
+    /// Model name, as priced by [`model_profile`]
+    fn model(&self) -> &str;
+
+    /// Called with the game state at the start of every round
+    fn observe(&mut self, _state: &GameState) {}
+
+    /// Propose an action for `player_id`
+    fn decide(&mut self, state: &GameState, player_id: &str) -> ProviderDecision;
+
+    /// Tokens taken by answers that were still being read when their
+    /// decision was returned and have ended since; with `wait`, waits for
+    /// all of them to end
+    fn late_usage(&mut self, _wait: bool) -> TokenUsage {
+        TokenUsage::default()
+    }
+}
+
+/// Creates the provider for a model, given a seed for reproducible play
+pub type ProviderFactory = Arc<dyn Fn(&str, u64) -> Box<dyn DecisionProvider> + Send + Sync>;
+
+/// Plays the model's baseline strategy and reports fixed usage
+pub struct MockProvider {
+    model: String,
+    profile: ModelProfile,
+    bot: Box<dyn Bot>,
+}
+
+impl MockProvider {
+    pub fn new(model: &str, seed: u64) -> Self {
+        let profile = model_profile(model);
+        Self { model: model.to_string(), profile, bot: bots::create_bot(profile.strategy, seed, None) }
+    }
+
+    /// [`MockProvider`]s for every model
+    pub fn factory() -> ProviderFactory {
+        Arc::new(|model, seed| Box::new(MockProvider::new(model, seed)) as Box<dyn DecisionProvider>)
+    }
+}
+
+impl DecisionProvider for MockProvider {
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn observe(&mut self, state: &GameState) {
+        self.bot.observe(state);
+    }
+
+    fn decide(&mut self, state: &GameState, player_id: &str) -> ProviderDecision {
+        ProviderDecision {
+            action: self.bot.choose_action(state, player_id),
+            prompt_tokens: MOCK_PROMPT_TOKENS,
+            completion_tokens: MOCK_COMPLETION_TOKENS,
+            latency_ms: self.profile.latency_ms,
+        }
+    }
+}
+
+/// What a player's models were asked and what it cost
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Usage {
+    /// Decisions that came to an action
+    pub decisions: u64,
+    /// Time spent deciding; agents that vote are asked in parallel, so a
+    /// vote takes as long as its slowest agent
+    pub decision_ms: u64,
+    /// Calls to any model
+    pub calls: u64,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub cost_usd: f64,
+    /// The calls, tokens and cost above by model
+    #[serde(default)]
+    pub by_model: BTreeMap<String, ModelUsage>,
+}
+
+/// What one model of a player was asked and what it cost
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ModelUsage {
+    pub calls: u64,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub cost_usd: f64,
+}
+
+impl Usage {
+    /// Charge `model` for `tokens` taken over `calls` calls
+    fn charge(&mut self, model: &str, calls: u64, tokens: TokenUsage) {
+        let cost = model_profile(model).cost(tokens.prompt_tokens, tokens.completion_tokens);
+        self.calls += calls;
+        self.prompt_tokens += tokens.prompt_tokens as u64;
+        self.completion_tokens += tokens.completion_tokens as u64;
+        self.cost_usd += cost;
+        let by_model = self.by_model.entry(model.to_string()).or_default();
+        by_model.calls += calls;
+        by_model.prompt_tokens += tokens.prompt_tokens as u64;
+        by_model.completion_tokens += tokens.completion_tokens as u64;
+        by_model.cost_usd += cost;
+    }
+}
+
+/// Shared record of a player's usage, read once the match is over
+pub type UsageMeter = Arc<Mutex<Usage>>;
+
+/// A collective (or single AI) playing through its agents' providers
+pub struct Collective {
+    agents: Vec<Box<dyn DecisionProvider>>,
+    coordination: Coordination,
+    /// Agent whose turn it is under [`Coordination::Rotate`]
+    turn: usize,
+    meter: UsageMeter,
+}
+
+impl Collective {
+    pub fn new(agents: Vec<Box<dyn DecisionProvider>>, coordination: Coordination, meter: UsageMeter) -> Self {
+        Self { agents, coordination, turn: 0, meter }
+    }
+
+    /// The preset's agents, each seeded from `seed`
+    pub fn of(config: CollectiveConfig, providers: &ProviderFactory, seed: u64, meter: UsageMeter) -> Self {
+        let agents = config.models().into_iter().enumerate()
+            .map(|(i, model)| providers(model, seed.wrapping_add(i as u64)))
+            .collect();
+        Self::new(agents, config.coordination(), meter)
+    }
+
+    /// A single model
+    pub fn single(model: &str, providers: &ProviderFactory, seed: u64, meter: UsageMeter) -> Self {
+        Self::new(vec![providers(model, seed)], Coordination::Vote, meter)
+    }
+
+    /// Charge the meter for answers that ended since the last decision, or
+    /// with `wait` for every answer still being read
+    fn charge_late_usage(&mut self, wait: bool) {
+        let mut usage = self.meter.lock().unwrap();
+        for agent in &mut self.agents {
+            let late = agent.late_usage(wait);
+            if late != TokenUsage::default() {
+                usage.charge(agent.model(), 0, late);
+            }
+        }
+    }
+
+    /// Ask `agents` and charge the meter for it
+    fn ask(&mut self, agents: std::ops::Range<usize>, state: &GameState, player_id: &str) -> Vec<GameAction> {
+        self.charge_late_usage(false);
+        let mut proposals = Vec::new();
+        let mut usage = self.meter.lock().unwrap();
+        let mut slowest = 0;
+        for agent in &mut self.agents[agents] {
+            let decision = agent.decide(state, player_id);
+            let tokens = TokenUsage { prompt_tokens: decision.prompt_tokens, completion_tokens: decision.completion_tokens };
+            usage.charge(agent.model(), 1, tokens);
+            slowest = slowest.max(decision.latency_ms);
+            proposals.extend(decision.action);
+        }
+        usage.decision_ms += slowest;
+        if !proposals.is_empty() {
+            usage.decisions += 1;
+        }
+        proposals
+    }
+}
+
+impl Bot for Collective {
+    fn observe(&mut self, state: &GameState) {
+        for agent in &mut self.agents {
+            agent.observe(state);
+        }
+    }
+
+    fn choose_action(&mut self, state: &GameState, player_id: &str) -> Option<GameAction> {
+        if self.agents.is_empty() {
+            return None;
+        }
+        match self.coordination {
+            Coordination::Rotate => {
+                let agent = self.turn % self.agents.len();
+                self.turn += 1;
+                self.ask(agent..agent + 1, state, player_id).pop()
+            }
+            Coordination::Vote => {
+                let proposals = self.ask(0..self.agents.len(), state, player_id);
+                let keys: Vec<String> = proposals.iter()
+                    .map(|action| serde_json::to_string(action).unwrap_or_default())
+                    .collect();
+                let votes = |key: &String| keys.iter().filter(|other| *other == key).count();
+                let mut winner = None;
+                let mut most = 0;
+                for (i, key) in keys.iter().enumerate() {
+                    let count = votes(key);
+                    if count > most {
+                        most = count;
+                        winner = Some(i);
+                    }
+                }
+                winner.map(|i| proposals[i].clone())
+            }
+        }
+    }
+
+    fn finish(&mut self) {
+        self.charge_late_usage(true);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{new_game, GameStatus, GameType};
+
+    /// Always proposes the same side
+    struct Fixed {
+        side: u8,
+    }
+
+    impl DecisionProvider for Fixed {
+        fn model(&self) -> &str {
+            "claude-haiku-3"
+        }
+
+        fn decide(&mut self, _state: &GameState, _player_id: &str) -> ProviderDecision {
+            ProviderDecision {
+                action: Some(GameAction::ChooseSide { side: self.side }),
+                prompt_tokens: 1000,
+                completion_tokens: 1000,
+                latency_ms: 100 * (self.side as u64 + 1),
+            }
+        }
+    }
+
+    fn collective(sides: &[u8], coordination: Coordination) -> (Collective, UsageMeter) {
+        let meter = UsageMeter::default();
+        let agents = sides.iter()
+            .map(|&side| Box::new(Fixed { side }) as Box<dyn DecisionProvider>)
+            .collect();
+        (Collective::new(agents, coordination, meter.clone()), meter)
+    }
+
+    fn side(action: Option<GameAction>) -> Option<u8> {
+        match action {
+            Some(GameAction::ChooseSide { side }) => Some(side),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_vote_plays_the_most_proposed_action() {
+        let mut state = new_game(GameType::MinorityGame, 5, Some(1));
+        state.status = GameStatus::Running;
+
+        let (mut voters, meter) = collective(&[0, 1, 1], Coordination::Vote);
+        assert_eq!(side(voters.choose_action(&state, "collective")), Some(1));
+        let usage = meter.lock().unwrap().clone();
+        assert_eq!((usage.decisions, usage.calls, usage.decision_ms), (1, 3, 200));
+        let haiku = model_profile("claude-haiku-3");
+        assert!((usage.cost_usd - 3.0 * (haiku.prompt_per_1k + haiku.completion_per_1k)).abs() < 1e-12);
+
+        // Ties go to the earliest proposer
+        let (mut tied, _) = collective(&[1, 0], Coordination::Vote);
+        assert_eq!(side(tied.choose_action(&state, "collective")), Some(1));
+    }
+
+    #[test]
+    fn test_rotation_asks_one_agent_at_a_time() {
+        let mut state = new_game(GameType::MinorityGame, 5, Some(1));
+        state.status = GameStatus::Running;
+
+        let (mut rotating, meter) = collective(&[0, 1], Coordination::Rotate);
+        let sides: Vec<Option<u8>> = (0..3).map(|_| side(rotating.choose_action(&state, "collective"))).collect();
+        assert_eq!(sides, [Some(0), Some(1), Some(0)]);
+        let usage = meter.lock().unwrap().clone();
+        assert_eq!((usage.decisions, usage.calls, usage.decision_ms), (3, 3, 400));
+    }
+
+    #[test]
+    fn test_presets_match_their_descriptions() {
+        assert_eq!(CollectiveConfig::OpusOrchestra.models(), vec!["claude-opus-4"; 6]);
+        assert_eq!(CollectiveConfig::LightweightLegion.models().len(), 32);
+        assert_eq!(CollectiveConfig::EmergenceEngine.coordination(), Coordination::Rotate);
+        let mock = MockProvider::new("claude-opus-4", 3);
+        assert_eq!(mock.profile, model_profile("claude-opus-4"));
+    }
+}
@@ -0,0 +1,111 @@
+//! Text embeddings for semantic games
+//!
+//! [`NgramEmbeddings`] computes the same hashed n-gram vectors, under the
+//! same provider name, as the server's memory index
+//! (`hal9_core::memory::EmbeddingGenerator`). A word scored in a game
+//! therefore sits in the same space as the memories it might be searched
+//! against. Other models plug in through [`EmbeddingProvider`].
+
+/// Dimension of the server's memory index embeddings
+pub const EMBEDDING_DIMENSION: usize = 384;
+
+/// Turns text into a fixed-size vector
+pub trait EmbeddingProvider: Send + Sync {
+    /// Identifies how embeddings were generated; embeddings from different
+    /// providers are not comparable
+    fn provider(&self) -> String;
+
+    /// Embedding of `text`; every vector from one provider has the same length
+    fn embed(&self, text: &str) -> Vec<f32>;
+}
+
+/// Hashed word, bigram and character trigram features, L2-normalized
+#[derive(Debug, Clone)]
+pub struct NgramEmbeddings {
+    dimension: usize,
+}
+
+impl NgramEmbeddings {
+    pub fn new(dimension: usize) -> Self {
+        Self { dimension }
+    }
+}
+
+impl Default for NgramEmbeddings {
+    fn default() -> Self {
+        Self::new(EMBEDDING_DIMENSION)
+    }
+}
+
+impl EmbeddingProvider for NgramEmbeddings {
+    fn provider(&self) -> String {
+        format!("hal9-ngram-{}", self.dimension)
+    }
+
+    fn embed(&self, text: &str) -> Vec<f32> {
+        let dimension = self.dimension;
+        let mut embedding = vec![0.0; dimension];
+        let text_lower = text.to_lowercase();
+        let words: Vec<&str> = text_lower.split_whitespace().collect();
+
+        for (word_idx, word) in words.iter().enumerate() {
+            embedding[hash(word.as_bytes()) as usize % dimension] += 1.0;
+
+            if word_idx > 0 {
+                let bigram = format!("{} {}", words[word_idx - 1], word);
+                embedding[(hash(bigram.as_bytes()) as usize % dimension + dimension / 4) % dimension] += 0.7;
+            }
+
+            // Byte trigrams: the same as character trigrams for ASCII, and
+            // safe for everything else
+            for trigram in word.as_bytes().windows(3) {
+                embedding[(hash(trigram) as usize % dimension + dimension / 2) % dimension] += 0.5;
+            }
+        }
+
+        let doc_length = words.len() as f32;
+        if doc_length > 0.0 {
+            for val in &mut embedding {
+                *val = (*val / doc_length.sqrt()).tanh();
+            }
+        }
+
+        normalize(&mut embedding);
+        embedding
+    }
+}
+
+/// FNV-1a
+fn hash(bytes: &[u8]) -> u64 {
+    const FNV_PRIME: u64 = 0x100000001b3;
+    const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+
+    bytes.iter().fold(FNV_OFFSET, |hash, byte| (hash ^ *byte as u64).wrapping_mul(FNV_PRIME))
+}
+
+/// Scale `v` to unit length, unless it is all zeros
+pub fn normalize(v: &mut [f32]) {
+    let magnitude: f32 = v.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if magnitude > 0.0 {
+        for val in v {
+            *val /= magnitude;
+        }
+    }
+}
+
+/// Cosine similarity, or 0 for mismatched or zero vectors
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() {
+        return 0.0;
+    }
+
+    let dot_product: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let magnitude_a: f32 = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let magnitude_b: f32 = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+
+    if magnitude_a > 0.0 && magnitude_b > 0.0 {
+        dot_product / (magnitude_a * magnitude_b)
+    } else {
+        0.0
+    }
+}
@@ -2,7 +2,7 @@
 //! Real-time multiplayer consciousness emergence game
 
 use axum::{
-    extract::{ws::WebSocket, ws::Message, WebSocketUpgrade, State, Path},
+    extract::{ws::WebSocket, ws::Message, WebSocketUpgrade, State, Path, Query},
     response::{Html, Response},
     routing::{get, post},
     middleware,
@@ -14,13 +14,38 @@ use std::{
     collections::HashMap,
     sync::Arc,
 };
-use tokio::sync::{broadcast, RwLock, Mutex};
+use tokio::sync::{broadcast, mpsc, MutexGuard, RwLock, Mutex};
 use tower_http::cors::CorsLayer;
 use uuid::Uuid;
 use tracing::info;
 
+mod ai_provider;
+mod analytics;
 mod auth;
+mod benchmark;
+mod board;
+mod bots;
+mod collective;
+mod embeddings;
+mod maze;
+mod moderation;
+mod oracle;
+mod replay;
+mod rules;
+mod shapeshifter;
+mod sync;
 mod user_store;
+use board::Board;
+use bots::{Bot, BotKind};
+use moderation::{ConnectionLimits, GameModeration, RateVerdict};
+use rand::Rng;
+use analytics::GameAnalytics;
+use maze::{Direction, MazeState, Redactor};
+use oracle::OracleState;
+use replay::{MatchLog, Playback};
+use rules::GameRules;
+use shapeshifter::ShapeshifterState;
+use sync::{GameChannel, GameDelta, SyncTracker};
 use auth::{Claims, LoginRequest, LoginResponse, RegisterRequest, UserInfo, 
            create_jwt, verify_password};
 use user_store::UserStore;
@@ -30,6 +55,10 @@ const BOARD_SIZE: usize = 19;
 const CONSCIOUSNESS_THRESHOLD: f32 = 0.8;
 const MAX_NEURONS_PER_PLAYER: usize = 50;
 const SIMULATION_TICK_MS: u64 = 100;
+const ROUND_DURATION_MS: u64 = 2000;
+const MAX_BOTS_PER_GAME: u32 = 16;
+const MAX_PLAYBACK_SPEED: f64 = 100.0;
+const MIN_PLAYBACK_SPEED: f64 = 0.1;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type")]
@@ -41,7 +70,7 @@ pub enum GameType {
     CollectiveMaze,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(tag = "type")]
 pub enum PlayerType {
     #[serde(rename = "hal9_collective")]
@@ -52,6 +81,10 @@ pub enum PlayerType {
     SingleAI {
         model: String,
     },
+    #[serde(rename = "bot")]
+    Bot {
+        strategy: BotKind,
+    },
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -62,10 +95,44 @@ pub struct GameState {
     pub round: u32,
     pub max_rounds: u32,
     pub players: HashMap<String, Player>,
-    pub board: Vec<Vec<Cell>>,
+    pub board: Board,
     pub consciousness_level: f32,
     pub events: Vec<GameEvent>,
     pub winner: Option<String>,
+    #[serde(default)]
+    pub decisions: Vec<DecisionRecord>,
+    #[serde(default)]
+    pub minority: MinorityState,
+    #[serde(default)]
+    pub shapeshifter: ShapeshifterState,
+    #[serde(default)]
+    pub oracle: OracleState,
+    #[serde(default)]
+    pub maze: MazeState,
+    /// Would give the rest of the match away; see [`GameView`]
+    #[serde(skip)]
+    pub seed: Option<u64>,
+    /// Seeded RNG and match log; server-side only
+    #[serde(skip)]
+    pub rules: GameRules,
+}
+
+/// One accepted action, recorded the same way for every player type
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DecisionRecord {
+    pub round: u32,
+    pub player_id: String,
+    pub action: GameAction,
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+}
+
+/// Minority game bookkeeping
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MinorityState {
+    /// Sides chosen so far this round, by player
+    pub choices: HashMap<String, u8>,
+    /// Winning (minority) side of every finished round
+    pub history: Vec<u8>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -76,7 +143,7 @@ pub enum GameStatus {
     Finished,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Player {
     pub id: String,
     pub name: String,
@@ -86,7 +153,7 @@ pub struct Player {
     pub color: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Cell {
     pub owner: Option<String>,
     pub neuron_strength: f32,
@@ -109,6 +176,22 @@ pub enum GameAction {
     StrengthenConnection { from: (usize, usize), to: (usize, usize) },
     #[serde(rename = "activate_special")]
     ActivateSpecial { ability: String },
+    #[serde(rename = "choose_side")]
+    ChooseSide { side: u8 },
+    #[serde(rename = "submit_word")]
+    SubmitWord { word: String },
+    /// Oracle paradox: [`oracle::commitment`] to this round's prediction
+    #[serde(rename = "commit")]
+    Commit { commitment: String },
+    /// Oracle paradox: open the commitment made earlier in the round
+    #[serde(rename = "reveal")]
+    Reveal { prediction: u8, nonce: String },
+    /// Collective maze: step your avatar one tile
+    #[serde(rename = "move")]
+    Move { direction: Direction },
+    /// Collective maze: message one player, or everyone if `to` is `None`
+    #[serde(rename = "send_message")]
+    SendMessage { to: Option<String>, text: String },
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -127,6 +210,20 @@ pub enum WebSocketMessage {
     #[serde(rename = "game_state")]
     GameState {
         state: GameState,
+        #[serde(default)]
+        seq: u64,
+    },
+    #[serde(rename = "game_delta")]
+    GameDelta {
+        delta: GameDelta,
+    },
+    #[serde(rename = "request_snapshot")]
+    RequestSnapshot,
+    /// Sent on connect; present `session_token` again to rejoin as `player_id`
+    #[serde(rename = "session")]
+    Session {
+        player_id: String,
+        session_token: String,
     },
     #[serde(rename = "error")]
     Error {
@@ -136,14 +233,26 @@ pub enum WebSocketMessage {
 
 struct AppState {
     games: Arc<RwLock<HashMap<String, Arc<Mutex<GameState>>>>>,
-    connections: Arc<RwLock<HashMap<String, broadcast::Sender<String>>>>,
+    connections: Arc<RwLock<HashMap<String, Arc<GameChannel>>>>,
     user_store: UserStore,
+    bots: Mutex<HashMap<String, Vec<(String, Box<dyn Bot>)>>>,
+    moderation: Mutex<HashMap<String, GameModeration>>,
 }
 
 #[tokio::main]
 async fn main() {
     tracing_subscriber::fmt::init();
     
+    // `benchmark <plan.json> <run-dir>` runs a tournament instead of serving
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("benchmark") {
+        if let Err(e) = benchmark::run_cli(&args[1..]).await {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+    
     // Initialize user store and create default admin
     let user_store = UserStore::new();
     user_store.init_default_admin().await;
@@ -152,6 +261,8 @@ async fn main() {
         games: Arc::new(RwLock::new(HashMap::new())),
         connections: Arc::new(RwLock::new(HashMap::new())),
         user_store,
+        bots: Mutex::new(HashMap::new()),
+        moderation: Mutex::new(HashMap::new()),
     });
     
     // Public routes (no auth required)
@@ -160,18 +271,33 @@ async fn main() {
         .route("/api/auth/register", post(register))
         .route("/api/auth/login", post(login))
         .route("/api/games", get(list_games))
-        .route("/api/games/:id", get(get_game));
+        .route("/api/games/:id", get(get_game))
+        .route("/api/games/:id/replay", get(get_replay))
+        .route("/api/games/:id/analytics", get(get_analytics));
     
     // Protected routes (auth required)
     let protected_routes = Router::new()
         .route("/api/games/create", post(create_game))
+        .route("/api/games/:id/start", post(start_game))
         .route("/api/auth/profile", get(get_profile))
         .route_layer(middleware::from_fn_with_state(state.clone(), auth_middleware));
     
+    // Moderation routes (admin only)
+    let admin_routes = Router::new()
+        .route("/api/games/:id/pause", post(pause_game))
+        .route("/api/games/:id/resume", post(resume_game))
+        .route("/api/games/:id/annul-round", post(annul_round))
+        .route("/api/games/:id/players/:player_id/kick", post(kick_player))
+        .route("/api/games/:id/players/:player_id/ban", post(ban_player))
+        .route_layer(middleware::from_fn(admin_middleware))
+        .route_layer(middleware::from_fn_with_state(state.clone(), auth_middleware));
+    
     let app = Router::new()
         .merge(public_routes)
         .merge(protected_routes)
+        .merge(admin_routes)
         .route("/ws/:id", get(websocket_handler))
+        .route("/ws/:id/playback", get(playback_handler))
         .layer(CorsLayer::permissive())
         .with_state(state);
     
@@ -193,31 +319,42 @@ async fn create_game(
     State(state): State<Arc<AppState>>,
     Json(params): Json<CreateGameParams>,
 ) -> Result<Json<GameInfo>, StatusCode> {
-    let game_id = Uuid::new_v4().to_string();
-    let game = GameState {
-        id: game_id.clone(),
-        game_type: params.game_type,
-        status: GameStatus::Waiting,
-        round: 0,
-        max_rounds: params.max_rounds.unwrap_or(20),
-        players: HashMap::new(),
-        board: vec![vec![Cell {
-            owner: None,
-            neuron_strength: 0.0,
-            connections: vec![],
-        }; BOARD_SIZE]; BOARD_SIZE],
-        consciousness_level: 0.0,
-        events: vec![],
-        winner: None,
-    };
+    let bot_count: u32 = params.bots.iter().map(|spec| spec.count).sum();
+    if bot_count > MAX_BOTS_PER_GAME {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    
+    let mut game = new_game(params.game_type, params.max_rounds.unwrap_or(20), params.seed);
+    let game_id = game.id.clone();
+    
+    // Bots join as regular players; a fixed seed gives reproducible runs
+    let mut bots = Vec::new();
+    for spec in &params.bots {
+        for _ in 0..spec.count {
+            let index = bots.len();
+            let player_id = format!("bot-{}-{}", spec.kind.as_str(), index);
+            let player = Player {
+                id: player_id.clone(),
+                name: format!("{} bot {}", spec.kind.as_str(), index + 1),
+                player_type: PlayerType::Bot { strategy: spec.kind },
+                score: 0,
+                neurons_placed: 0,
+                color: generate_player_color(&game.players),
+            };
+            rules::add_player(&mut game, player);
+            let bot = bots::create_bot(spec.kind, game.rules.rng().gen(), spec.budget_ms);
+            bots.push((player_id, bot));
+        }
+    }
     
     state.games.write().await.insert(
         game_id.clone(),
         Arc::new(Mutex::new(game))
     );
+    state.bots.lock().await.insert(game_id.clone(), bots);
+    state.moderation.lock().await.insert(game_id.clone(), GameModeration::new(&game_id));
     
-    let (tx, _) = broadcast::channel(100);
-    state.connections.write().await.insert(game_id.clone(), tx);
+    state.connections.write().await.insert(game_id.clone(), Arc::new(GameChannel::new(100)));
     
     Ok(Json(GameInfo {
         id: game_id,
@@ -225,6 +362,134 @@ async fn create_game(
     }))
 }
 
+/// Start a waiting game and run its rounds
+async fn start_game(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> Result<Json<GameInfo>, StatusCode> {
+    let game = state.games.read().await.get(&id).cloned()
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let channel = state.connections.read().await.get(&id).cloned();
+    
+    {
+        let mut g = game.lock().await;
+        rules::start_game(&mut g).map_err(|_| StatusCode::CONFLICT)?;
+        if let Some(channel) = &channel {
+            channel.publish(&g);
+        }
+    }
+    
+    let bots = state.bots.lock().await.remove(&id).unwrap_or_default();
+    tokio::spawn(run_rounds(game, bots, channel));
+    
+    Ok(Json(GameInfo {
+        id,
+        status: "running".to_string(),
+    }))
+}
+
+/// Round loop: bots act through the same rules as everyone else, then the
+/// phase ends at its deadline; most games have one phase per round
+async fn run_rounds(
+    game: Arc<Mutex<GameState>>,
+    mut bots: Vec<(String, Box<dyn Bot>)>,
+    channel: Option<Arc<GameChannel>>,
+) {
+    bots.sort_by(|a, b| a.0.cmp(&b.0));
+    
+    loop {
+        let wait = {
+            let mut g = lock_unpaused(&game).await;
+            if g.status != GameStatus::Running {
+                break;
+            }
+            
+            // Bots see what a player in their place would, see [`maze::view`]
+            for (player_id, bot) in bots.iter_mut() {
+                bot.observe(&maze::view(&g, player_id));
+            }
+            for (player_id, bot) in bots.iter_mut() {
+                if g.status != GameStatus::Running {
+                    break;
+                }
+                // Kicked bots sit the rest of the game out
+                if !g.players.contains_key(player_id.as_str()) {
+                    continue;
+                }
+                let action = bot.choose_action(&maze::view(&g, player_id), player_id);
+                if let Some(action) = action {
+                    if let Err(e) = rules::apply_action(&mut g, player_id, action) {
+                        tracing::warn!("Bot {} action rejected: {}", player_id, e);
+                    }
+                }
+            }
+            if let Some(channel) = &channel {
+                channel.publish(&g);
+            }
+            phase_left(&g)
+        };
+        
+        // Leave the rest of the phase to human and AI players
+        tokio::time::sleep(wait).await;
+        
+        // A round paused by a moderator ends once the game resumes
+        let mut g = lock_unpaused(&game).await;
+        rules::end_phase(&mut g);
+        if let Some(channel) = &channel {
+            channel.publish(&g);
+        }
+        if g.status != GameStatus::Running {
+            break;
+        }
+    }
+}
+
+/// Time until the phase in progress is due to end
+fn phase_left(state: &GameState) -> tokio::time::Duration {
+    match (&state.game_type, state.oracle.deadline) {
+        (GameType::OracleParadox, Some(deadline)) => {
+            (deadline - chrono::Utc::now()).to_std().unwrap_or_default()
+        }
+        _ => tokio::time::Duration::from_millis(ROUND_DURATION_MS),
+    }
+}
+
+/// Lock the game once it is not paused
+async fn lock_unpaused(game: &Mutex<GameState>) -> MutexGuard<'_, GameState> {
+    loop {
+        let g = game.lock().await;
+        if g.status != GameStatus::Paused {
+            return g;
+        }
+        drop(g);
+        tokio::time::sleep(tokio::time::Duration::from_millis(SIMULATION_TICK_MS)).await;
+    }
+}
+
+/// A fresh game with an empty board, seeded randomly unless `seed` is given
+fn new_game(game_type: GameType, max_rounds: u32, seed: Option<u64>) -> GameState {
+    let seed = seed.unwrap_or_else(rand::random);
+    GameState {
+        id: Uuid::new_v4().to_string(),
+        game_type,
+        status: GameStatus::Waiting,
+        round: 0,
+        max_rounds,
+        players: HashMap::new(),
+        board: Board::new(BOARD_SIZE),
+        consciousness_level: 0.0,
+        events: vec![],
+        winner: None,
+        decisions: vec![],
+        minority: MinorityState::default(),
+        shapeshifter: ShapeshifterState::default(),
+        oracle: OracleState::default(),
+        maze: MazeState::default(),
+        seed: Some(seed),
+        rules: GameRules::new(seed),
+    }
+}
+
 async fn list_games(
     State(state): State<Arc<AppState>>,
 ) -> Json<Vec<GameInfo>> {
@@ -242,40 +507,91 @@ async fn list_games(
     Json(game_list)
 }
 
+/// A game as served over HTTP, see [`maze::public_view`], with its seed
+/// once it is finished
+#[derive(Debug, Serialize)]
+struct GameView {
+    #[serde(flatten)]
+    state: GameState,
+    seed: Option<u64>,
+}
+
+impl GameView {
+    fn of(state: &GameState) -> Self {
+        let seed = state.seed.filter(|_| state.status == GameStatus::Finished);
+        Self { state: maze::public_view(state).into_owned(), seed }
+    }
+}
+
 async fn get_game(
     State(state): State<Arc<AppState>>,
     Path(id): Path<String>,
-) -> Result<Json<GameState>, StatusCode> {
+) -> Result<Json<GameView>, StatusCode> {
     let games = state.games.read().await;
     
     if let Some(game) = games.get(&id) {
         let g = game.lock().await;
-        Ok(Json(g.clone()))
+        Ok(Json(GameView::of(&g)))
     } else {
         Err(StatusCode::NOT_FOUND)
     }
 }
 
+/// Everything needed to rebuild the game once it is finished, see [`replay`]
+async fn get_replay(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> Result<Json<MatchLog>, StatusCode> {
+    let games = state.games.read().await;
+    let game = games.get(&id).ok_or(StatusCode::NOT_FOUND)?;
+    let g = game.lock().await;
+    MatchLog::of_finished(&g).map(Json).ok_or(StatusCode::FORBIDDEN)
+}
+
+/// Scores and per-game breakdowns, see [`analytics`]
+async fn get_analytics(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> Result<Json<GameAnalytics>, StatusCode> {
+    let games = state.games.read().await;
+    let game = games.get(&id).ok_or(StatusCode::NOT_FOUND)?;
+    let g = game.lock().await;
+    Ok(Json(GameAnalytics::of(&g)))
+}
+
+#[derive(Debug, Deserialize)]
+struct SocketParams {
+    /// Session token from an earlier connection, to rejoin as the same player
+    session: Option<String>,
+    /// Access token of an authenticated player
+    token: Option<String>,
+}
+
 async fn websocket_handler(
     ws: WebSocketUpgrade,
     State(state): State<Arc<AppState>>,
     Path(game_id): Path<String>,
+    Query(params): Query<SocketParams>,
 ) -> Response {
-    ws.on_upgrade(move |socket| handle_socket(socket, state, game_id))
+    ws.on_upgrade(move |socket| handle_socket(socket, state, game_id, params))
+}
+
+fn error_message(message: impl Into<String>) -> String {
+    serde_json::to_string(&WebSocketMessage::Error { message: message.into() }).unwrap()
 }
 
 async fn handle_socket(
     socket: WebSocket,
     state: Arc<AppState>,
     game_id: String,
+    params: SocketParams,
 ) {
     let (mut sender, mut receiver) = socket.split();
-    let player_id = Uuid::new_v4().to_string();
     
     // Subscribe to game broadcasts
     let connections = state.connections.read().await;
-    let tx = if let Some(tx) = connections.get(&game_id) {
-        tx.clone()
+    let channel = if let Some(channel) = connections.get(&game_id) {
+        channel.clone()
     } else {
         let _ = sender.send(Message::Text(
             serde_json::to_string(&WebSocketMessage::Error {
@@ -286,36 +602,188 @@ async fn handle_socket(
     };
     drop(connections);
     
-    let mut rx = tx.subscribe();
+    // Banned sessions and accounts are turned away, see [`moderation`]
+    let user_id = match params.token.as_deref().map(auth::validate_jwt) {
+        None => None,
+        Some(Ok(claims)) => Some(claims.sub),
+        Some(Err(_)) => {
+            let _ = sender.send(Message::Text(error_message("Invalid access token"))).await;
+            return;
+        }
+    };
+    let session_token = params.session.unwrap_or_else(|| Uuid::new_v4().to_string());
+    let admission = match state.moderation.lock().await.get_mut(&game_id) {
+        Some(moderation) => moderation.admit(&session_token, user_id.as_deref(), &state.user_store).await,
+        None => Err("Game not found".to_string()),
+    };
+    let (player_id, mut removed) = match admission {
+        Ok(admission) => (admission.player_id, admission.removed),
+        Err(message) => {
+            let _ = sender.send(Message::Text(error_message(message))).await;
+            let _ = sender.send(Message::Close(None)).await;
+            return;
+        }
+    };
+    
+    let mut rx = channel.subscribe();
+    
+    // Replies meant for this client only, starting with its session and a
+    // snapshot to sync from
+    let (direct_tx, mut direct_rx) = mpsc::unbounded_channel();
+    let _ = direct_tx.send(serde_json::to_string(&WebSocketMessage::Session {
+        player_id: player_id.clone(),
+        session_token,
+    }).unwrap());
+    // Collective maze players only get to see their part of the maze
+    let mut redactor = None;
+    if let Some(game) = state.games.read().await.get(&game_id) {
+        let g = game.lock().await;
+        if let GameType::CollectiveMaze = g.game_type {
+            redactor = Some(Redactor::new(&player_id));
+        }
+        let _ = direct_tx.send(channel.snapshot(&g));
+    }
     
     // Handle incoming messages
     let state_clone = state.clone();
     let game_id_clone = game_id.clone();
     let player_id_clone = player_id.clone();
-    let tx_clone = tx.clone();
+    let channel_clone = channel.clone();
     
-    tokio::spawn(async move {
+    let receive_task = tokio::spawn(async move {
+        let mut limits = ConnectionLimits::new();
         while let Some(msg) = receiver.next().await {
             if let Ok(Message::Text(text)) = msg {
+                match limits.on_message(std::time::Instant::now()) {
+                    RateVerdict::Allow => {}
+                    RateVerdict::Throttle => {
+                        let _ = direct_tx.send(error_message("Rate limit exceeded, messages are being dropped"));
+                        continue;
+                    }
+                    RateVerdict::Drop => continue,
+                    RateVerdict::Disconnect => {
+                        tracing::warn!("Disconnecting {} from game {}: repeated rate limit violations",
+                            player_id_clone, game_id_clone);
+                        let _ = direct_tx.send(error_message("Disconnected for sending too many messages"));
+                        break;
+                    }
+                }
                 if let Ok(ws_msg) = serde_json::from_str::<WebSocketMessage>(&text) {
                     handle_game_message(
                         ws_msg,
                         &state_clone,
                         &game_id_clone,
                         &player_id_clone,
-                        &tx_clone
+                        &channel_clone,
+                        &direct_tx,
+                        &mut limits,
                     ).await;
                 }
             }
         }
     });
     
-    // Send game updates to client
-    while let Ok(msg) = rx.recv().await {
+    // Send game updates to client. A lagging client skips messages and
+    // resyncs once it notices the gap in sequence numbers.
+    loop {
+        let msg = tokio::select! {
+            msg = rx.recv() => match msg {
+                Ok(msg) => msg,
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+            msg = direct_rx.recv() => match msg {
+                Some(msg) => msg,
+                // The client left or was disconnected for flooding
+                None => break,
+            },
+            Ok(()) = removed.changed() => {
+                let message = removed.borrow_and_update().clone().unwrap_or_default();
+                let _ = sender.send(Message::Text(error_message(message))).await;
+                break;
+            }
+        };
+        let msg = match &mut redactor {
+            Some(redactor) => redactor.redact(msg),
+            None => msg,
+        };
         if sender.send(Message::Text(msg)).await.is_err() {
             break;
         }
     }
+    receive_task.abort();
+    let _ = sender.send(Message::Close(None)).await;
+}
+
+#[derive(Debug, Deserialize)]
+struct PlaybackParams {
+    /// Multiple of real time; 2.0 plays twice as fast
+    speed: Option<f64>,
+}
+
+async fn playback_handler(
+    ws: WebSocketUpgrade,
+    State(state): State<Arc<AppState>>,
+    Path(game_id): Path<String>,
+    Query(params): Query<PlaybackParams>,
+) -> Response {
+    let speed = params.speed.unwrap_or(1.0).clamp(MIN_PLAYBACK_SPEED, MAX_PLAYBACK_SPEED);
+    ws.on_upgrade(move |socket| handle_playback(socket, state, game_id, speed))
+}
+
+/// Re-broadcast a finished match to one client, with the original pacing
+/// scaled by `speed`. Messages are the same snapshots and deltas a live
+/// game sends, so the frontend needs no special handling.
+async fn handle_playback(
+    mut socket: WebSocket,
+    state: Arc<AppState>,
+    game_id: String,
+    speed: f64,
+) {
+    let log = match state.games.read().await.get(&game_id) {
+        Some(game) => MatchLog::of_finished(&*game.lock().await).ok_or("Game is not finished yet"),
+        None => Err("Game not found"),
+    };
+    let log = match log {
+        Ok(log) => log,
+        Err(message) => {
+            let _ = socket.send(Message::Text(error_message(message))).await;
+            return;
+        }
+    };
+    
+    let mut playback = Playback::new(log);
+    let mut tracker = SyncTracker::new();
+    let first = tracker.next_message(playback.state());
+    if socket.send(Message::Text(serde_json::to_string(&first).unwrap())).await.is_err() {
+        return;
+    }
+    
+    let mut previous: Option<chrono::DateTime<chrono::Utc>> = None;
+    loop {
+        let timestamp = match playback.peek() {
+            Some(entry) => entry.timestamp,
+            None => break,
+        };
+        // Idle stretches are capped at a round so playback never stalls
+        if let Some(previous) = previous {
+            let gap = (timestamp - previous).to_std().unwrap_or_default()
+                .min(tokio::time::Duration::from_millis(ROUND_DURATION_MS));
+            tokio::time::sleep(gap.div_f64(speed)).await;
+        }
+        previous = Some(timestamp);
+        
+        if let Err(e) = playback.step() {
+            let _ = socket.send(Message::Text(
+                serde_json::to_string(&WebSocketMessage::Error { message: e }).unwrap()
+            )).await;
+            return;
+        }
+        let message = tracker.next_message(playback.state());
+        if socket.send(Message::Text(serde_json::to_string(&message).unwrap())).await.is_err() {
+            return;
+        }
+    }
 }
 
 async fn handle_game_message(
@@ -323,7 +791,9 @@ async fn handle_game_message(
     state: &Arc<AppState>,
     game_id: &str,
     player_id: &str,
-    tx: &broadcast::Sender<String>,
+    channel: &GameChannel,
+    direct: &mpsc::UnboundedSender<String>,
+    limits: &mut ConnectionLimits,
 ) {
     match message {
         WebSocketMessage::JoinGame { player_name, player_type, .. } => {
@@ -331,6 +801,12 @@ async fn handle_game_message(
             if let Some(game) = games.get(game_id) {
                 let mut g = game.lock().await;
                 
+                // Rejoining on a known session keeps the player as they were
+                if g.players.contains_key(player_id) {
+                    let _ = direct.send(channel.snapshot(&g));
+                    return;
+                }
+                
                 let player = Player {
                     id: player_id.to_string(),
                     name: player_name,
@@ -340,18 +816,10 @@ async fn handle_game_message(
                     color: generate_player_color(&g.players),
                 };
                 
-                g.players.insert(player_id.to_string(), player.clone());
-                
-                g.events.push(GameEvent {
-                    timestamp: chrono::Utc::now(),
-                    event_type: "player_joined".to_string(),
-                    description: format!("Player {} joined the game", player.name),
-                });
+                rules::add_player(&mut g, player);
                 
-                // Broadcast updated game state
-                let _ = tx.send(serde_json::to_string(&WebSocketMessage::GameState {
-                    state: g.clone()
-                }).unwrap());
+                // Broadcast what changed
+                channel.publish(&g);
             }
         }
         WebSocketMessage::GameAction { action } => {
@@ -359,70 +827,55 @@ async fn handle_game_message(
             if let Some(game) = games.get(game_id) {
                 let mut g = game.lock().await;
                 
-                if g.status != GameStatus::Running {
-                    return;
+                let round = g.round;
+                let flagged = limits.on_action(std::time::Instant::now(), round);
+                if let Some(gap) = flagged {
+                    let reason = format!(
+                        "actions {}ms apart, faster than the {}ms simulation tick allows",
+                        gap.as_millis(), SIMULATION_TICK_MS,
+                    );
+                    rules::flag_player(&mut g, player_id, &reason);
                 }
                 
-                // Process game action
-                match action {
-                    GameAction::PlaceNeuron { x, y } => {
-                        if x < BOARD_SIZE && y < BOARD_SIZE {
-                            g.board[y][x] = Cell {
-                                owner: Some(player_id.to_string()),
-                                neuron_strength: 1.0,
-                                connections: vec![],
-                            };
-                            
-                            if let Some(player) = g.players.get_mut(player_id) {
-                                player.neurons_placed += 1;
-                                player.score += 10;
-                            }
-                            
-                            // Update consciousness level
-                            g.consciousness_level = calculate_consciousness(&g.board);
-                            
-                            g.events.push(GameEvent {
-                                timestamp: chrono::Utc::now(),
-                                event_type: "neuron_placed".to_string(),
-                                description: format!("Neuron placed at ({}, {})", x, y),
-                            });
-                        }
-                    }
-                    GameAction::StrengthenConnection { from: _, to: _ } => {
-                        // Implement connection logic
-                    }
-                    GameAction::ActivateSpecial { ability: _ } => {
-                        // Implement special abilities
+                if let Err(e) = rules::apply_action(&mut g, player_id, action) {
+                    tracing::debug!("Action from {} rejected: {}", player_id, e);
+                    if flagged.is_some() {
+                        channel.publish(&g);
                     }
+                    return;
                 }
                 
-                // Check win conditions
-                if g.consciousness_level >= CONSCIOUSNESS_THRESHOLD {
-                    g.status = GameStatus::Finished;
-                    g.winner = Some(player_id.to_string());
-                }
-                
-                // Broadcast updated game state
-                let _ = tx.send(serde_json::to_string(&WebSocketMessage::GameState {
-                    state: g.clone()
-                }).unwrap());
+                // Broadcast what changed
+                channel.publish(&g);
+            }
+        }
+        WebSocketMessage::RequestSnapshot => {
+            let games = state.games.read().await;
+            if let Some(game) = games.get(game_id) {
+                let g = game.lock().await;
+                let _ = direct.send(channel.snapshot(&g));
             }
         }
         _ => {}
     }
 }
 
-fn calculate_consciousness(board: &Vec<Vec<Cell>>) -> f32 {
+fn calculate_consciousness(board: &Board) -> f32 {
     let mut total_strength = 0.0;
     let mut connections = 0;
     
-    for row in board {
-        for cell in row {
-            total_strength += cell.neuron_strength;
-            connections += cell.connections.len();
-        }
+    // Empty cells contribute nothing, so summing only stored cells in
+    // row-major order gives exactly the dense board's result
+    for (_, cell) in board.iter() {
+        total_strength += cell.neuron_strength;
+        connections += cell.connections.len();
     }
     
+    consciousness_from_totals(total_strength, connections)
+}
+
+/// Consciousness for a board with the given total strength and connection count
+fn consciousness_from_totals(total_strength: f32, connections: usize) -> f32 {
     // Simple consciousness calculation
     let density = total_strength / (BOARD_SIZE * BOARD_SIZE) as f32;
     let connectivity = connections as f32 / (total_strength * 4.0).max(1.0);
@@ -450,6 +903,17 @@ fn generate_player_color(existing_players: &HashMap<String, Player>) -> String {
 struct CreateGameParams {
     game_type: GameType,
     max_rounds: Option<u32>,
+    #[serde(default)]
+    bots: Vec<BotSpec>,
+    seed: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BotSpec {
+    #[serde(rename = "type")]
+    kind: BotKind,
+    count: u32,
+    budget_ms: Option<u64>,
 }
 
 #[derive(Debug, Serialize)]
@@ -517,6 +981,105 @@ async fn get_profile(claims: Claims) -> Result<Json<UserInfo>, StatusCode> {
     }))
 }
 
+// Moderation handlers
+
+/// Apply a moderator's change to a game and broadcast it
+async fn moderate_game(
+    state: &AppState,
+    id: &str,
+    change: fn(&mut GameState) -> Result<(), String>,
+) -> Result<Json<GameInfo>, StatusCode> {
+    let game = state.games.read().await.get(id).cloned()
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let channel = state.connections.read().await.get(id).cloned();
+    
+    let mut g = game.lock().await;
+    change(&mut g).map_err(|_| StatusCode::CONFLICT)?;
+    if let Some(channel) = &channel {
+        channel.publish(&g);
+    }
+    
+    Ok(Json(GameInfo {
+        id: id.to_string(),
+        status: format!("{:?}", g.status),
+    }))
+}
+
+async fn pause_game(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> Result<Json<GameInfo>, StatusCode> {
+    moderate_game(&state, &id, rules::pause_game).await
+}
+
+async fn resume_game(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> Result<Json<GameInfo>, StatusCode> {
+    moderate_game(&state, &id, rules::resume_game).await
+}
+
+/// Undo the round in progress, see [`rules::annul_round`]
+async fn annul_round(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> Result<Json<GameInfo>, StatusCode> {
+    moderate_game(&state, &id, rules::annul_round).await
+}
+
+async fn kick_player(
+    State(state): State<Arc<AppState>>,
+    Path((id, player_id)): Path<(String, String)>,
+) -> Result<StatusCode, StatusCode> {
+    remove_player(&state, &id, &player_id, false).await
+}
+
+async fn ban_player(
+    State(state): State<Arc<AppState>>,
+    Path((id, player_id)): Path<(String, String)>,
+) -> Result<StatusCode, StatusCode> {
+    remove_player(&state, &id, &player_id, true).await
+}
+
+/// Take a player out of a game and close their connections; a banned
+/// player cannot rejoin. Banning works on players already kicked.
+async fn remove_player(
+    state: &AppState,
+    id: &str,
+    player_id: &str,
+    ban: bool,
+) -> Result<StatusCode, StatusCode> {
+    let game = state.games.read().await.get(id).cloned()
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let channel = state.connections.read().await.get(id).cloned();
+    
+    let removed = {
+        let mut g = game.lock().await;
+        let reason = if ban { "banned" } else { "kicked" };
+        let removed = rules::remove_player(&mut g, player_id, reason).is_ok();
+        if removed {
+            if let Some(channel) = &channel {
+                channel.publish(&g);
+            }
+        }
+        removed
+    };
+    
+    let mut moderation = state.moderation.lock().await;
+    let connected = match moderation.get_mut(id) {
+        Some(moderation) if ban => moderation.ban(player_id, &state.user_store).await,
+        Some(moderation) => moderation.kick(player_id, "You were kicked from this game"),
+        None => false,
+    };
+    
+    if removed || connected {
+        info!("Player {} {} from game {}", player_id, if ban { "banned" } else { "kicked" }, id);
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(StatusCode::NOT_FOUND)
+    }
+}
+
 async fn auth_middleware(
     State(_state): State<Arc<AppState>>,
     mut request: axum::http::Request<axum::body::Body>,
@@ -540,4 +1103,16 @@ async fn auth_middleware(
     request.extensions_mut().insert(claims);
     
     Ok(next.run(request).await)
+}
+
+/// Admin-only routes; runs after [`auth_middleware`] has checked the token
+async fn admin_middleware(
+    request: axum::http::Request<axum::body::Body>,
+    next: axum::middleware::Next,
+) -> Result<axum::response::Response, StatusCode> {
+    match request.extensions().get::<Claims>() {
+        Some(claims) if claims.role == auth::Role::Admin => Ok(next.run(request).await),
+        Some(_) => Err(StatusCode::FORBIDDEN),
+        None => Err(StatusCode::UNAUTHORIZED),
+    }
 }
\ No newline at end of file
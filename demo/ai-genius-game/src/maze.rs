@@ -0,0 +1,683 @@
+//! Collective maze: explore a maze together, barely able to talk
+//!
+//! Every game plays a maze of its own, generated from the game's seed by
+//! [`MazeState::generate`]. All avatars start at the entrance and move one
+//! tile a round. A player scores [`EXPLORE_POINTS`] for every tile no
+//! avatar stood on before, and [`GOAL_POINTS`] for reaching a goal tile
+//! first, plus [`SPEED_POINTS`] for every round left to play. The game ends
+//! with the round the last goal is reached in.
+//!
+//! Players only see the tiles and avatars within [`VISION_RADIUS`] of their
+//! own avatar, and only talk through messages, of at most
+//! [`MESSAGE_BYTES_PER_ROUND`] bytes a round each, to one player or to
+//! everyone. [`view`] redacts the game down to what one player may see,
+//! and a [`Redactor`] does the same to the messages sent to their
+//! connection. Connections that are not playing spectate and see
+//! everything. Over HTTP, where anyone may ask, [`public_view`] shows only
+//! the outline of a maze still being played.
+
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::{BTreeSet, HashMap};
+
+use crate::rules::GameRules;
+use crate::{GameState, GameStatus, GameType, WebSocketMessage};
+
+/// Width and height of every maze; odd, so that the walls between
+/// corridors fall on even rows and columns
+pub const MAZE_SIZE: usize = 11;
+
+/// Goal tiles in every maze
+pub const GOALS: usize = 2;
+
+/// How many tiles away, along either axis, an avatar sees
+pub const VISION_RADIUS: usize = 2;
+
+/// Message bytes each player may send a round
+pub const MESSAGE_BYTES_PER_ROUND: usize = 64;
+
+/// Points for being the first to stand on a tile
+pub const EXPLORE_POINTS: i32 = 1;
+
+/// Points for being the first to reach a goal
+pub const GOAL_POINTS: i32 = 30;
+
+/// Points for every round left to play when a goal is reached
+pub const SPEED_POINTS: i32 = 2;
+
+/// Mixed into the game seed for the maze's own RNG stream
+const MAZE_STREAM: u64 = 0x4d41_5a45_5354_524d;
+
+/// Directions an avatar moves in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Direction {
+    North,
+    East,
+    South,
+    West,
+}
+
+impl Direction {
+    pub const ALL: [Direction; 4] = [Direction::North, Direction::East, Direction::South, Direction::West];
+
+    /// The position one step from `(x, y)`, or `None` off a `size` maze
+    pub fn step(self, (x, y): (usize, usize), size: usize) -> Option<(usize, usize)> {
+        let (x, y) = match self {
+            Direction::North => (x, y.checked_sub(1)?),
+            Direction::East => (x + 1, y),
+            Direction::South => (x, y + 1),
+            Direction::West => (x.checked_sub(1)?, y),
+        };
+        (x < size && y < size).then_some((x, y))
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Tile {
+    /// Out of the viewer's sight
+    #[default]
+    Unknown,
+    Wall,
+    Open,
+    Goal,
+}
+
+impl Tile {
+    pub fn is_passable(self) -> bool {
+        matches!(self, Tile::Open | Tile::Goal)
+    }
+}
+
+/// The maze and everything that happened in it
+///
+/// Players get it through [`MazeState::view_for`], never whole.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MazeState {
+    /// Width and height; 0 before the game starts
+    pub size: usize,
+    /// Row-major; [`Tile::Unknown`] where the viewer cannot see
+    pub tiles: Vec<Tile>,
+    /// Where every avatar starts
+    pub entrance: (usize, usize),
+    /// Goal tiles in the maze, seen or not
+    pub goals: usize,
+    /// Avatar positions, by player
+    pub avatars: HashMap<String, (usize, usize)>,
+    /// Tiles some avatar has stood on
+    pub visited: BTreeSet<(usize, usize)>,
+    /// Players who moved this round
+    pub moved: BTreeSet<String>,
+    /// Goals reached, in the order they were reached
+    pub reached: Vec<GoalReached>,
+    /// Every message sent, oldest first
+    pub messages: Vec<MazeMessage>,
+    /// Message bytes sent this round, by player
+    pub bandwidth: HashMap<String, usize>,
+    /// Every finished round
+    pub rounds: Vec<MazeRound>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GoalReached {
+    pub round: u32,
+    pub player_id: String,
+    pub position: (usize, usize),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MazeMessage {
+    pub round: u32,
+    pub from: String,
+    /// `None` for a message to everyone
+    pub to: Option<String>,
+    pub text: String,
+}
+
+/// A finished round's traffic, and how far the players had got by its end
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MazeRound {
+    pub round: u32,
+    pub messages: usize,
+    pub message_bytes: usize,
+    /// Tiles visited so far
+    pub visited: usize,
+    /// Goals reached so far
+    pub goals_reached: usize,
+}
+
+impl MazeState {
+    /// The maze of a game seeded with `seed`: a perfect maze carved by a
+    /// randomized depth-first search from the entrance, with [`GOALS`] goal
+    /// tiles out of sight of it
+    pub fn generate(seed: u64) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed ^ MAZE_STREAM);
+        let size = MAZE_SIZE;
+        let entrance = (1, 1);
+        let mut tiles = vec![Tile::Wall; size * size];
+        tiles[entrance.1 * size + entrance.0] = Tile::Open;
+
+        // Corridors run through odd positions; two steps reach the next one
+        let mut stack = vec![entrance];
+        while let Some(&at) = stack.last() {
+            let unvisited: Vec<(usize, usize)> = Direction::ALL.iter()
+                .filter_map(|direction| direction.step(direction.step(at, size)?, size))
+                .filter(|&(x, y)| x % 2 == 1 && y % 2 == 1 && tiles[y * size + x] == Tile::Wall)
+                .collect();
+            match unvisited.choose(&mut rng) {
+                Some(&(x, y)) => {
+                    tiles[((at.1 + y) / 2) * size + (at.0 + x) / 2] = Tile::Open;
+                    tiles[y * size + x] = Tile::Open;
+                    stack.push((x, y));
+                }
+                None => {
+                    stack.pop();
+                }
+            }
+        }
+
+        let mut candidates: Vec<usize> = (0..tiles.len())
+            .filter(|&i| tiles[i] == Tile::Open && !in_sight(entrance, (i % size, i / size)))
+            .collect();
+        candidates.shuffle(&mut rng);
+        for &i in candidates.iter().take(GOALS) {
+            tiles[i] = Tile::Goal;
+        }
+
+        Self {
+            size,
+            goals: GOALS.min(candidates.len()),
+            tiles,
+            entrance,
+            visited: BTreeSet::from([entrance]),
+            ..Default::default()
+        }
+    }
+
+    /// The tile at `(x, y)`; walls all around the maze
+    pub fn tile(&self, (x, y): (usize, usize)) -> Tile {
+        if x < self.size && y < self.size {
+            self.tiles[y * self.size + x]
+        } else {
+            Tile::Wall
+        }
+    }
+
+    /// Put a player's avatar at the entrance, unless they have one
+    pub fn add_avatar(&mut self, player_id: &str) {
+        self.avatars.entry(player_id.to_string()).or_insert(self.entrance);
+    }
+
+    /// Take a player's avatar out of the maze; their messages stay
+    pub fn remove_player(&mut self, player_id: &str) {
+        self.avatars.remove(player_id);
+        self.moved.remove(player_id);
+        self.bandwidth.remove(player_id);
+    }
+
+    /// Whether every goal has been reached
+    pub fn is_complete(&self) -> bool {
+        self.size > 0 && self.reached.len() >= self.goals
+    }
+
+    /// Whether someone reached the goal at `position` already
+    pub fn is_reached(&self, position: (usize, usize)) -> bool {
+        self.reached.iter().any(|goal| goal.position == position)
+    }
+
+    /// Where `player_id` would end up moving in `direction`
+    pub fn validate_move(&self, player_id: &str, direction: Direction) -> Result<(usize, usize), String> {
+        let at = *self.avatars.get(player_id)
+            .ok_or_else(|| "You have no avatar in the maze".to_string())?;
+        if self.moved.contains(player_id) {
+            return Err("Already moved this round".to_string());
+        }
+        match direction.step(at, self.size) {
+            Some(to) if self.tile(to).is_passable() => Ok(to),
+            _ => Err("There is a wall in the way".to_string()),
+        }
+    }
+
+    /// Check a message against the sender's bandwidth left this round
+    pub fn validate_message(&self, player_id: &str, text: &str) -> Result<(), String> {
+        if text.is_empty() {
+            return Err("Messages cannot be empty".to_string());
+        }
+        let used = self.bandwidth.get(player_id).copied().unwrap_or_default();
+        if used + text.len() > MESSAGE_BYTES_PER_ROUND {
+            return Err(format!(
+                "Message of {} bytes exceeds the {} bytes left this round",
+                text.len(),
+                MESSAGE_BYTES_PER_ROUND - used,
+            ));
+        }
+        Ok(())
+    }
+
+    /// Close round `round`'s books and give everyone a move and their
+    /// bandwidth back
+    pub fn end_round(&mut self, round: u32) {
+        let sent: Vec<&MazeMessage> = self.messages.iter().filter(|m| m.round == round).collect();
+        self.rounds.push(MazeRound {
+            round,
+            messages: sent.len(),
+            message_bytes: sent.iter().map(|m| m.text.len()).sum(),
+            visited: self.visited.len(),
+            goals_reached: self.reached.len(),
+        });
+        self.moved.clear();
+        self.bandwidth.clear();
+    }
+
+    /// Go back to `checkpoint`, keeping the avatars of the players in the
+    /// game now
+    pub fn rewind<'a>(&mut self, checkpoint: MazeState, players: impl Iterator<Item = &'a String>) {
+        let avatars = std::mem::take(&mut self.avatars);
+        *self = checkpoint;
+        let mut players: Vec<&String> = players.collect();
+        self.avatars.retain(|id, _| players.contains(&id));
+        players.retain(|id| avatars.contains_key(*id));
+        for id in players {
+            self.add_avatar(id);
+        }
+    }
+
+    /// What is known of the maze from outside it: its size, entrance and
+    /// goal count, and how far the players have got
+    pub fn outline(&self) -> MazeState {
+        MazeState {
+            size: self.size,
+            tiles: vec![Tile::Unknown; self.tiles.len()],
+            entrance: self.entrance,
+            goals: self.goals,
+            rounds: self.rounds.clone(),
+            ..MazeState::default()
+        }
+    }
+
+    /// What `viewer` may see: the tiles, visits and avatars in sight of
+    /// their avatar, the messages to or from them and their own bandwidth
+    pub fn view_for(&self, viewer: &str) -> MazeState {
+        let at = self.avatars.get(viewer).copied();
+        let sees = |position: (usize, usize)| at.is_some_and(|at| in_sight(at, position));
+        MazeState {
+            size: self.size,
+            tiles: self.tiles.iter().enumerate()
+                .map(|(i, tile)| if sees((i % self.size, i / self.size)) { *tile } else { Tile::Unknown })
+                .collect(),
+            entrance: self.entrance,
+            goals: self.goals,
+            avatars: self.avatars.iter()
+                .filter(|(id, position)| id.as_str() == viewer || sees(**position))
+                .map(|(id, position)| (id.clone(), *position))
+                .collect(),
+            visited: self.visited.iter().copied().filter(|position| sees(*position)).collect(),
+            moved: self.moved.iter().filter(|id| id.as_str() == viewer).cloned().collect(),
+            reached: self.reached.clone(),
+            messages: self.messages.iter()
+                .filter(|m| m.from == viewer || m.to.as_deref().is_none_or(|to| to == viewer))
+                .cloned()
+                .collect(),
+            bandwidth: self.bandwidth.iter()
+                .filter(|(id, _)| id.as_str() == viewer)
+                .map(|(id, bytes)| (id.clone(), *bytes))
+                .collect(),
+            rounds: self.rounds.clone(),
+        }
+    }
+}
+
+/// Whether an avatar at `from` sees `to`
+fn in_sight(from: (usize, usize), to: (usize, usize)) -> bool {
+    from.0.abs_diff(to.0) <= VISION_RADIUS && from.1.abs_diff(to.1) <= VISION_RADIUS
+}
+
+/// Points for reaching a goal first in `round` of a `max_rounds` game
+pub fn goal_points(round: u32, max_rounds: u32) -> i32 {
+    GOAL_POINTS + SPEED_POINTS * max_rounds.saturating_sub(round + 1) as i32
+}
+
+/// The game as `viewer` may see it: in a collective maze they play, the
+/// maze redacted by [`MazeState::view_for`] and only their own decisions.
+/// Anyone else sees the whole game.
+pub fn view<'a>(state: &'a GameState, viewer: &str) -> Cow<'a, GameState> {
+    if !matches!(state.game_type, GameType::CollectiveMaze) || !state.players.contains_key(viewer) {
+        return Cow::Borrowed(state);
+    }
+    let mut view = state.clone();
+    view.maze = state.maze.view_for(viewer);
+    view.decisions.retain(|decision| decision.player_id == viewer);
+    // The seed would give the whole maze away
+    view.seed = None;
+    view.rules = GameRules::default().simulation();
+    Cow::Owned(view)
+}
+
+/// The game as served to anyone over HTTP: a collective maze still being
+/// played shows only its [`outline`](MazeState::outline) and no decisions,
+/// which would trace the paths through it
+pub fn public_view(state: &GameState) -> Cow<'_, GameState> {
+    if !matches!(state.game_type, GameType::CollectiveMaze) || state.status == GameStatus::Finished {
+        return Cow::Borrowed(state);
+    }
+    let mut view = state.clone();
+    view.maze = state.maze.outline();
+    view.decisions.clear();
+    Cow::Owned(view)
+}
+
+/// Redacts the game messages sent to one collective maze connection once
+/// its player is in the game
+///
+/// Whether they are is followed from the messages themselves: snapshots
+/// list every player, and deltas the players who joined or left.
+pub struct Redactor {
+    viewer: String,
+    playing: bool,
+}
+
+impl Redactor {
+    pub fn new(viewer: &str) -> Self {
+        Self { viewer: viewer.to_string(), playing: false }
+    }
+
+    /// `raw` as the viewer may see it
+    pub fn redact(&mut self, raw: String) -> String {
+        let message = match serde_json::from_str::<WebSocketMessage>(&raw) {
+            Ok(WebSocketMessage::GameState { state, seq }) => {
+                self.playing = state.players.contains_key(&self.viewer);
+                if !self.playing {
+                    return raw;
+                }
+                WebSocketMessage::GameState { state: view(&state, &self.viewer).into_owned(), seq }
+            }
+            Ok(WebSocketMessage::GameDelta { mut delta }) => {
+                if delta.players.iter().any(|player| player.id == self.viewer) {
+                    self.playing = true;
+                }
+                if delta.removed_players.contains(&self.viewer) {
+                    self.playing = false;
+                }
+                if !self.playing {
+                    return raw;
+                }
+                delta.maze = delta.maze.map(|maze| maze.view_for(&self.viewer));
+                delta.decisions.retain(|decision| decision.player_id == self.viewer);
+                WebSocketMessage::GameDelta { delta }
+            }
+            _ => return raw,
+        };
+        serde_json::to_string(&message).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analytics::GameAnalytics;
+    use crate::rules;
+    use crate::sync::GameChannel;
+    use crate::{new_game, GameAction, GameView, Player, PlayerType};
+    use std::collections::VecDeque;
+
+    fn game(players: &[&str], max_rounds: u32, seed: u64) -> GameState {
+        let mut state = new_game(GameType::CollectiveMaze, max_rounds, Some(seed));
+        for id in players {
+            rules::add_player(&mut state, Player {
+                id: id.to_string(),
+                name: id.to_string(),
+                player_type: PlayerType::SingleAI { model: "human".to_string() },
+                score: 0,
+                neurons_placed: 0,
+                color: "#00ffff".to_string(),
+            });
+        }
+        rules::start_game(&mut state).unwrap();
+        state
+    }
+
+    /// Shortest way from `from` to `to` through the whole maze
+    fn path(maze: &MazeState, from: (usize, usize), to: (usize, usize)) -> Vec<Direction> {
+        let mut came_from: HashMap<(usize, usize), ((usize, usize), Direction)> = HashMap::new();
+        let mut queue = VecDeque::from([from]);
+        while let Some(at) = queue.pop_front() {
+            if at == to {
+                break;
+            }
+            for direction in Direction::ALL {
+                let Some(next) = direction.step(at, maze.size) else { continue };
+                if maze.tile(next).is_passable() && next != from && !came_from.contains_key(&next) {
+                    came_from.insert(next, (at, direction));
+                    queue.push_back(next);
+                }
+            }
+        }
+        let mut steps = Vec::new();
+        let mut at = to;
+        while at != from {
+            let (previous, direction) = came_from[&at];
+            steps.push(direction);
+            at = previous;
+        }
+        steps.reverse();
+        steps
+    }
+
+    fn goals(maze: &MazeState) -> Vec<(usize, usize)> {
+        (0..maze.tiles.len())
+            .filter(|&i| maze.tiles[i] == Tile::Goal)
+            .map(|i| (i % maze.size, i / maze.size))
+            .collect()
+    }
+
+    fn send(to: Option<&str>, text: &str) -> GameAction {
+        GameAction::SendMessage { to: to.map(str::to_string), text: text.to_string() }
+    }
+
+    #[test]
+    fn test_maze_generation_is_deterministic_per_seed() {
+        assert_eq!(MazeState::generate(7), MazeState::generate(7));
+        assert_ne!(MazeState::generate(7).tiles, MazeState::generate(8).tiles);
+        assert_eq!(game(&["ada"], 20, 7).maze.tiles, MazeState::generate(7).tiles);
+
+        for seed in 0..20 {
+            let maze = MazeState::generate(seed);
+            let goals = goals(&maze);
+            assert_eq!((goals.len(), maze.goals), (GOALS, GOALS));
+            assert!(goals.iter().all(|goal| !in_sight(maze.entrance, *goal)));
+            for i in 0..MAZE_SIZE {
+                for edge in [(i, 0), (0, i), (i, MAZE_SIZE - 1), (MAZE_SIZE - 1, i)] {
+                    assert_eq!(maze.tile(edge), Tile::Wall);
+                }
+            }
+            // A perfect maze: every corridor tile can be reached
+            for i in 0..maze.tiles.len() {
+                let position = (i % MAZE_SIZE, i / MAZE_SIZE);
+                if position.0 % 2 == 1 && position.1 % 2 == 1 {
+                    assert!(maze.tile(position).is_passable(), "seed {} at {:?}", seed, position);
+                    assert!(position == maze.entrance || !path(&maze, maze.entrance, position).is_empty());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_views_show_players_only_what_they_can_see() {
+        let mut state = game(&["ada", "bo", "cy"], 20, 3);
+        let far = (0..state.maze.tiles.len())
+            .map(|i| (i % MAZE_SIZE, i / MAZE_SIZE))
+            .find(|position| state.maze.tile(*position) == Tile::Open && !in_sight(state.maze.entrance, *position))
+            .unwrap();
+        state.maze.avatars.insert("bo".to_string(), far);
+        let step = Direction::ALL.into_iter().find(|d| state.maze.validate_move("ada", *d).is_ok()).unwrap();
+        rules::apply_action(&mut state, "ada", GameAction::Move { direction: step }).unwrap();
+        rules::apply_action(&mut state, "ada", send(Some("bo"), "meet at the entrance")).unwrap();
+        rules::apply_action(&mut state, "cy", send(None, "heading east")).unwrap();
+        rules::apply_action(&mut state, "bo", send(Some("cy"), "nothing here")).unwrap();
+
+        let view = view(&state, "ada");
+        let ada = view.maze.avatars["ada"];
+        for (i, tile) in view.maze.tiles.iter().enumerate() {
+            let position = (i % MAZE_SIZE, i / MAZE_SIZE);
+            let expected = if in_sight(ada, position) { state.maze.tile(position) } else { Tile::Unknown };
+            assert_eq!(*tile, expected, "at {:?}", position);
+        }
+        assert_eq!(view.maze.avatars.len(), 2, "bo is out of sight");
+        assert!(view.maze.avatars.contains_key("cy"));
+        assert!(view.maze.visited.iter().all(|position| in_sight(ada, *position)));
+        let texts: Vec<&str> = view.maze.messages.iter().map(|m| m.text.as_str()).collect();
+        assert_eq!(texts, ["meet at the entrance", "heading east"]);
+        assert_eq!(view.maze.bandwidth.keys().collect::<Vec<_>>(), ["ada"]);
+        assert!(view.decisions.iter().all(|d| d.player_id == "ada"));
+        assert_eq!(view.decisions.len(), 2);
+        assert_eq!(view.seed, None);
+
+        // Private messages reach only the two players they are between
+        let texts: Vec<String> = super::view(&state, "bo").maze.messages.iter().map(|m| m.text.clone()).collect();
+        assert_eq!(texts, ["meet at the entrance", "heading east", "nothing here"]);
+        let texts: Vec<String> = super::view(&state, "cy").maze.messages.iter().map(|m| m.text.clone()).collect();
+        assert_eq!(texts, ["heading east", "nothing here"]);
+
+        // Spectators see everything
+        assert!(matches!(super::view(&state, "watcher"), Cow::Borrowed(_)));
+
+        // Broadcasts are redacted the same way
+        let channel = GameChannel::new(16);
+        let mut rx = channel.subscribe();
+        channel.publish(&state);
+        let mut redactor = Redactor::new("ada");
+        let mut spectator = Redactor::new("watcher");
+        let snapshot = rx.try_recv().unwrap();
+        assert_eq!(spectator.redact(snapshot.clone()), snapshot);
+        match serde_json::from_str(&redactor.redact(snapshot)).unwrap() {
+            WebSocketMessage::GameState { state: redacted, .. } => {
+                assert_eq!(redacted.maze, view.maze);
+                assert_eq!(redacted.decisions.len(), 2);
+                assert_eq!(redacted.seed, None);
+            }
+            other => panic!("expected a snapshot, got {:?}", other),
+        }
+
+        rules::end_round(&mut state);
+        rules::apply_action(&mut state, "bo", send(Some("cy"), "still nothing")).unwrap();
+        let back = match step {
+            Direction::North => Direction::South,
+            Direction::South => Direction::North,
+            Direction::East => Direction::West,
+            Direction::West => Direction::East,
+        };
+        rules::apply_action(&mut state, "ada", GameAction::Move { direction: back }).unwrap();
+        channel.publish(&state);
+        let delta = rx.try_recv().unwrap();
+        assert_eq!(spectator.redact(delta.clone()), delta);
+        match serde_json::from_str(&redactor.redact(delta)).unwrap() {
+            WebSocketMessage::GameDelta { delta } => {
+                assert_eq!(delta.maze.unwrap(), state.maze.view_for("ada"));
+                assert_eq!(delta.decisions.len(), 1);
+                assert_eq!(delta.decisions[0].player_id, "ada");
+            }
+            other => panic!("expected a delta, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_game_fetched_over_http_hides_the_maze_until_finished() {
+        let mut state = game(&["ada", "bo"], 2, 3);
+        let step = Direction::ALL.into_iter().find(|d| state.maze.validate_move("ada", *d).is_ok()).unwrap();
+        rules::apply_action(&mut state, "ada", GameAction::Move { direction: step }).unwrap();
+
+        let served = serde_json::to_value(GameView::of(&state)).unwrap();
+        let maze: MazeState = serde_json::from_value(served["maze"].clone()).unwrap();
+        assert_eq!(maze, state.maze.outline());
+        assert!(maze.tiles.iter().all(|tile| *tile == Tile::Unknown));
+        assert!(maze.avatars.is_empty());
+        assert_eq!(served["decisions"], serde_json::json!([]));
+
+        while state.status == GameStatus::Running {
+            rules::end_round(&mut state);
+        }
+        let served = serde_json::to_value(GameView::of(&state)).unwrap();
+        assert_eq!(served["maze"], serde_json::to_value(&state.maze).unwrap());
+    }
+
+    #[test]
+    fn test_messages_are_capped_per_round() {
+        let mut state = game(&["ada", "bo"], 20, 5);
+        let long = "x".repeat(MESSAGE_BYTES_PER_ROUND - 4);
+        rules::apply_action(&mut state, "ada", send(Some("bo"), &long)).unwrap();
+        let err = rules::apply_action(&mut state, "ada", send(None, "north!")).unwrap_err();
+        assert_eq!(err, "Message of 6 bytes exceeds the 4 bytes left this round");
+        rules::apply_action(&mut state, "ada", send(None, "go")).unwrap();
+        assert!(rules::apply_action(&mut state, "ada", send(None, "")).is_err());
+        assert!(rules::apply_action(&mut state, "ada", send(Some("ada"), "me")).is_err());
+        assert!(rules::apply_action(&mut state, "ada", send(Some("zed"), "who")).is_err());
+        // bo's bandwidth is their own
+        rules::apply_action(&mut state, "bo", send(None, &long)).unwrap();
+
+        rules::end_round(&mut state);
+        rules::apply_action(&mut state, "ada", send(None, &long)).unwrap();
+        assert_eq!(state.maze.rounds[0].messages, 3);
+        assert_eq!(state.maze.rounds[0].message_bytes, 2 * long.len() + 2);
+    }
+
+    #[test]
+    fn test_scripted_two_player_run_reaches_the_goals() {
+        let max_rounds = 100;
+        let mut state = game(&["ada", "bo"], max_rounds, 11);
+        let goals = goals(&state.maze);
+        let mut routes = [
+            VecDeque::from(path(&state.maze, state.maze.entrance, goals[0])),
+            VecDeque::from(path(&state.maze, state.maze.entrance, goals[1])),
+        ];
+
+        // One move a round, and never into a wall
+        let first = routes[0][0];
+        let wall = Direction::ALL.into_iter().find(|d| state.maze.validate_move("ada", *d).is_err()).unwrap();
+        assert!(rules::apply_action(&mut state, "ada", GameAction::Move { direction: wall }).is_err());
+        assert!(rules::apply_action(&mut state, "ada", GameAction::PlaceNeuron { x: 1, y: 1 }).is_err());
+
+        // Each heads for a goal, telling the other where it is
+        let text = format!("goal at {},{}", goals[0].0, goals[0].1);
+        rules::apply_action(&mut state, "ada", send(Some("bo"), &text)).unwrap();
+        while state.status == GameStatus::Running {
+            for (id, route) in ["ada", "bo"].into_iter().zip(routes.iter_mut()) {
+                if let Some(direction) = route.pop_front() {
+                    rules::apply_action(&mut state, id, GameAction::Move { direction }).unwrap();
+                }
+            }
+            if state.round == 0 {
+                let again = GameAction::Move { direction: first };
+                assert!(rules::apply_action(&mut state, "ada", again).is_err());
+            }
+            rules::end_round(&mut state);
+        }
+
+        let maze = &state.maze;
+        assert!(maze.is_complete());
+        assert!(routes.iter().all(VecDeque::is_empty));
+        let longest = path(maze, maze.entrance, goals[0]).len().max(path(maze, maze.entrance, goals[1]).len());
+        assert_eq!(state.round, longest as u32);
+        assert_eq!(maze.reached.last().unwrap().round, longest as u32 - 1);
+        assert!(state.winner.is_some());
+
+        // Every point came from a new tile or a goal
+        let goal_points: i32 = maze.reached.iter().map(|goal| goal_points(goal.round, max_rounds)).sum();
+        let explored = (maze.visited.len() - 1) as i32 * EXPLORE_POINTS;
+        assert_eq!(state.players.values().map(|p| p.score).sum::<i32>(), explored + goal_points);
+
+        let analytics = GameAnalytics::of(&state).maze.unwrap();
+        assert_eq!(analytics.rounds.len(), longest);
+        assert_eq!(analytics.rounds[0].message_bytes, text.len());
+        assert_eq!(analytics.players["ada"].message_bytes, text.len());
+        assert_eq!(analytics.players["bo"].messages, 0);
+        assert_eq!(analytics.completed_round, Some(longest as u32 - 1));
+        assert_eq!(analytics.goals_reached, GOALS);
+        assert!(analytics.coverage > 0.0 && analytics.coverage <= 1.0);
+
+        // The same script plays out the same way
+        let replayed = game(&["ada", "bo"], max_rounds, 11);
+        assert_eq!(replayed.maze.tiles, maze.tiles);
+    }
+}
@@ -0,0 +1,371 @@
+//! Moderation and abuse protection
+//!
+//! Every WebSocket connection plays under a session token, which the client
+//! presents again to rejoin as the same player. Admins can kick a player,
+//! which closes their connections, or ban them, which also refuses their
+//! session token from then on. Authenticated players are banned by account
+//! too, recorded in the [`UserStore`], so a fresh session does not get them
+//! back in.
+//!
+//! Connections are limited to [`MAX_MESSAGES_PER_SECOND`]. Messages over the
+//! cap are dropped, and a connection that goes over it in
+//! [`MAX_RATE_VIOLATIONS`] different seconds is disconnected. Game actions
+//! from one connection closer together than a simulation tick come faster
+//! than anyone can play, so the player is flagged in the game's event log,
+//! at most once a round.
+
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
+use uuid::Uuid;
+
+use crate::user_store::UserStore;
+use crate::SIMULATION_TICK_MS;
+
+/// Messages a connection may send per second
+pub const MAX_MESSAGES_PER_SECOND: u32 = 10;
+
+/// Seconds over the message cap before a connection is disconnected
+pub const MAX_RATE_VIOLATIONS: u32 = 3;
+
+/// A connection let into a game
+pub struct Admission {
+    pub player_id: String,
+    /// Set to a message for the player when they are kicked or banned
+    pub removed: watch::Receiver<Option<String>>,
+}
+
+struct Session {
+    player_id: String,
+    user_id: Option<String>,
+    removed: watch::Sender<Option<String>>,
+}
+
+/// Sessions and bans of one game
+pub struct GameModeration {
+    game_id: String,
+    /// Sessions by token
+    sessions: HashMap<String, Session>,
+    banned_sessions: HashSet<String>,
+}
+
+impl GameModeration {
+    pub fn new(game_id: &str) -> Self {
+        Self {
+            game_id: game_id.to_string(),
+            sessions: HashMap::new(),
+            banned_sessions: HashSet::new(),
+        }
+    }
+
+    /// Let in a connection presenting `session_token`, authenticated as
+    /// `user_id` if it is. A known session plays as the same player again.
+    pub async fn admit(&mut self, session_token: &str, user_id: Option<&str>, users: &UserStore) -> Result<Admission, String> {
+        if self.banned_sessions.contains(session_token) {
+            return Err("You are banned from this game".to_string());
+        }
+        if let Some(user_id) = user_id {
+            if users.is_banned_from_game(user_id, &self.game_id).await {
+                return Err("You are banned from this game".to_string());
+            }
+        }
+
+        let session = self.sessions.entry(session_token.to_string()).or_insert_with(|| Session {
+            player_id: Uuid::new_v4().to_string(),
+            user_id: None,
+            removed: watch::channel(None).0,
+        });
+        if let Some(user_id) = user_id {
+            session.user_id = Some(user_id.to_string());
+        }
+        Ok(Admission {
+            player_id: session.player_id.clone(),
+            removed: session.removed.subscribe(),
+        })
+    }
+
+    /// Close every connection of `player_id`; false if they have none
+    pub fn kick(&mut self, player_id: &str, message: &str) -> bool {
+        let mut found = false;
+        for session in self.sessions.values().filter(|s| s.player_id == player_id) {
+            session.removed.send_replace(Some(message.to_string()));
+            found = true;
+        }
+        found
+    }
+
+    /// Refuse the sessions and account of `player_id` from now on and close
+    /// their connections; false if they have no session
+    pub async fn ban(&mut self, player_id: &str, users: &UserStore) -> bool {
+        let sessions: Vec<(String, Option<String>)> = self.sessions.iter()
+            .filter(|(_, s)| s.player_id == player_id)
+            .map(|(token, s)| (token.clone(), s.user_id.clone()))
+            .collect();
+
+        for (token, user_id) in &sessions {
+            self.banned_sessions.insert(token.clone());
+            if let Some(user_id) = user_id {
+                if let Err(e) = users.ban_from_game(user_id, &self.game_id).await {
+                    tracing::warn!("Could not ban user {} from game {}: {}", user_id, self.game_id, e);
+                }
+            }
+        }
+        self.kick(player_id, "You were banned from this game");
+        !sessions.is_empty()
+    }
+}
+
+/// What to do with a message, given the connection's rate limit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RateVerdict {
+    Allow,
+    /// First message over the cap this second: drop it and tell the client
+    Throttle,
+    /// Drop it silently
+    Drop,
+    /// Too many violations: close the connection
+    Disconnect,
+}
+
+/// Message rate limit and action pacing of one connection
+#[derive(Debug, Default)]
+pub struct ConnectionLimits {
+    window_start: Option<Instant>,
+    in_window: u32,
+    violations: u32,
+    last_action: Option<Instant>,
+    flagged_round: Option<u32>,
+}
+
+impl ConnectionLimits {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Count a message received at `now`
+    pub fn on_message(&mut self, now: Instant) -> RateVerdict {
+        match self.window_start {
+            Some(start) if now.duration_since(start) < Duration::from_secs(1) => {}
+            _ => {
+                self.window_start = Some(now);
+                self.in_window = 0;
+            }
+        }
+        self.in_window += 1;
+
+        if self.in_window <= MAX_MESSAGES_PER_SECOND {
+            return RateVerdict::Allow;
+        }
+        if self.in_window == MAX_MESSAGES_PER_SECOND + 1 {
+            self.violations += 1;
+            if self.violations >= MAX_RATE_VIOLATIONS {
+                return RateVerdict::Disconnect;
+            }
+            return RateVerdict::Throttle;
+        }
+        RateVerdict::Drop
+    }
+
+    /// Count a game action received at `now` during `round`. Returns the
+    /// gap to the previous action when it is shorter than a simulation tick
+    /// and the player has not been flagged this round yet.
+    pub fn on_action(&mut self, now: Instant, round: u32) -> Option<Duration> {
+        let previous = self.last_action.replace(now)?;
+        let gap = now.duration_since(previous);
+        if gap >= Duration::from_millis(SIMULATION_TICK_MS) || self.flagged_round == Some(round) {
+            return None;
+        }
+        self.flagged_round = Some(round);
+        Some(gap)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::replay::{final_state, MatchLog};
+    use crate::{
+        calculate_consciousness, new_game, rules, GameAction, GameState, GameStatus, GameType,
+        Player, PlayerType,
+    };
+
+    fn player(id: &str) -> Player {
+        Player {
+            id: id.to_string(),
+            name: id.to_string(),
+            player_type: PlayerType::SingleAI { model: "human".to_string() },
+            score: 0,
+            neurons_placed: 0,
+            color: "#00ffff".to_string(),
+        }
+    }
+
+    fn started_game(game_type: GameType) -> GameState {
+        let mut state = new_game(game_type, 10, Some(7));
+        rules::add_player(&mut state, player("alice"));
+        rules::add_player(&mut state, player("bob"));
+        rules::start_game(&mut state).unwrap();
+        state
+    }
+
+    fn place(state: &mut GameState, id: &str, x: usize, y: usize) {
+        rules::apply_action(state, id, GameAction::PlaceNeuron { x, y }).unwrap();
+    }
+
+    fn score(state: &GameState, id: &str) -> (i32, u32) {
+        let player = &state.players[id];
+        (player.score, player.neurons_placed)
+    }
+
+    #[test]
+    fn test_rate_limit_disconnects_after_repeated_violations() {
+        let start = Instant::now();
+        let mut limits = ConnectionLimits::new();
+
+        // Staying at the cap is fine indefinitely
+        for second in 0..10 {
+            let now = start + Duration::from_secs(second);
+            for _ in 0..MAX_MESSAGES_PER_SECOND {
+                assert_eq!(limits.on_message(now), RateVerdict::Allow);
+            }
+        }
+
+        let mut limits = ConnectionLimits::new();
+        for second in 0..MAX_RATE_VIOLATIONS as u64 {
+            let now = start + Duration::from_secs(second);
+            for _ in 0..MAX_MESSAGES_PER_SECOND {
+                assert_eq!(limits.on_message(now), RateVerdict::Allow);
+            }
+            if second + 1 < MAX_RATE_VIOLATIONS as u64 {
+                assert_eq!(limits.on_message(now), RateVerdict::Throttle);
+                assert_eq!(limits.on_message(now), RateVerdict::Drop);
+            } else {
+                assert_eq!(limits.on_message(now), RateVerdict::Disconnect);
+            }
+        }
+    }
+
+    #[test]
+    fn test_impossible_action_rate_is_flagged_once_per_round() {
+        let start = Instant::now();
+        let tick = Duration::from_millis(SIMULATION_TICK_MS);
+        let mut limits = ConnectionLimits::new();
+
+        assert_eq!(limits.on_action(start, 0), None);
+        assert_eq!(limits.on_action(start + tick, 0), None);
+        let gap = limits.on_action(start + tick + Duration::from_millis(5), 0);
+        assert_eq!(gap, Some(Duration::from_millis(5)));
+        assert_eq!(limits.on_action(start + tick + Duration::from_millis(6), 0), None);
+        assert!(limits.on_action(start + tick + Duration::from_millis(7), 1).is_some());
+
+        let mut state = started_game(GameType::ConsciousnessEmergence);
+        rules::flag_player(&mut state, "alice", "actions 5ms apart");
+        let event = state.events.last().unwrap();
+        assert_eq!(event.event_type, "player_flagged");
+        assert_eq!(event.description, "Player alice flagged: actions 5ms apart");
+    }
+
+    #[tokio::test]
+    async fn test_ban_is_enforced_on_rejoin() {
+        let users = UserStore::new();
+        let user = users.create_user("griefer".to_string(), "g@example.com".to_string(), "secret".to_string())
+            .await
+            .unwrap();
+        let mut moderation = GameModeration::new("game-1");
+
+        let mut admission = moderation.admit("session-1", Some(&user.id), &users).await.unwrap();
+        let player_id = admission.player_id.clone();
+
+        // A kicked player may rejoin as the same player
+        assert!(moderation.kick(&player_id, "You were kicked from this game"));
+        admission.removed.changed().await.unwrap();
+        let mut admission = moderation.admit("session-1", Some(&user.id), &users).await.unwrap();
+        assert_eq!(admission.player_id, player_id);
+
+        assert!(moderation.ban(&player_id, &users).await);
+        admission.removed.changed().await.unwrap();
+        assert_eq!(admission.removed.borrow().as_deref(), Some("You were banned from this game"));
+
+        // Neither the session nor the account gets back in
+        assert!(moderation.admit("session-1", None, &users).await.is_err());
+        assert!(moderation.admit("session-2", Some(&user.id), &users).await.is_err());
+        assert!(users.is_banned_from_game(&user.id, "game-1").await);
+
+        // Other players and other games are unaffected
+        assert!(moderation.admit("session-3", None, &users).await.is_ok());
+        let mut other_game = GameModeration::new("game-2");
+        assert!(other_game.admit("session-1", Some(&user.id), &users).await.is_ok());
+    }
+
+    #[test]
+    fn test_round_annulment_reverts_scores() {
+        let mut state = started_game(GameType::ConsciousnessEmergence);
+        place(&mut state, "alice", 0, 0);
+        rules::end_round(&mut state);
+        let board = state.board.clone();
+        let events = state.events.len();
+
+        place(&mut state, "alice", 1, 0);
+        place(&mut state, "bob", 2, 0);
+        rules::apply_action(&mut state, "alice", GameAction::StrengthenConnection { from: (1, 0), to: (2, 0) }).unwrap();
+        rules::add_player(&mut state, player("carol"));
+        place(&mut state, "carol", 3, 0);
+        assert_eq!(score(&state, "alice"), (25, 2));
+
+        rules::annul_round(&mut state).unwrap();
+
+        assert_eq!(score(&state, "alice"), (10, 1));
+        assert_eq!(score(&state, "bob"), (0, 0));
+        assert_eq!(score(&state, "carol"), (0, 0));
+        assert_eq!(state.board, board);
+        assert_eq!(state.consciousness_level, calculate_consciousness(&state.board));
+        assert_eq!(state.decisions.len(), 1);
+        assert_eq!(state.round, 1);
+        assert_eq!(state.events.last().unwrap().event_type, "round_annulled");
+        assert!(state.events.len() > events);
+
+        // The annulled round can be played again and the match still replays
+        place(&mut state, "bob", 1, 0);
+        rules::end_round(&mut state);
+        let rebuilt = final_state(MatchLog::of(&state)).unwrap();
+        assert_eq!(serde_json::to_value(&rebuilt).unwrap(), serde_json::to_value(&state).unwrap());
+    }
+
+    #[test]
+    fn test_annulled_minority_round_awards_nothing() {
+        let mut state = started_game(GameType::MinorityGame);
+        rules::apply_action(&mut state, "alice", GameAction::ChooseSide { side: 1 }).unwrap();
+        rules::apply_action(&mut state, "bob", GameAction::ChooseSide { side: 1 }).unwrap();
+        rules::annul_round(&mut state).unwrap();
+
+        assert!(state.minority.choices.is_empty());
+        rules::end_round(&mut state);
+        assert_eq!(score(&state, "alice").0 + score(&state, "bob").0, 0);
+    }
+
+    #[test]
+    fn test_pause_and_removal() {
+        let mut state = started_game(GameType::ConsciousnessEmergence);
+        rules::pause_game(&mut state).unwrap();
+
+        assert_eq!(state.status, GameStatus::Paused);
+        assert!(rules::apply_action(&mut state, "alice", GameAction::PlaceNeuron { x: 0, y: 0 }).is_err());
+        rules::end_round(&mut state);
+        assert_eq!(state.round, 0);
+        rules::annul_round(&mut state).unwrap();
+
+        rules::remove_player(&mut state, "bob", "banned").unwrap();
+        assert!(!state.players.contains_key("bob"));
+        assert_eq!(state.events.last().unwrap().description, "Player bob was banned");
+        assert!(rules::remove_player(&mut state, "bob", "banned").is_err());
+
+        rules::resume_game(&mut state).unwrap();
+        place(&mut state, "alice", 0, 0);
+        let rebuilt = final_state(MatchLog::of(&state)).unwrap();
+        assert_eq!(serde_json::to_value(&rebuilt).unwrap(), serde_json::to_value(&state).unwrap());
+
+        // Annulling needs a round in progress
+        let mut waiting = new_game(GameType::ConsciousnessEmergence, 10, Some(1));
+        assert!(rules::annul_round(&mut waiting).is_err());
+    }
+}
@@ -0,0 +1,375 @@
+//! Oracle paradox: predict what everyone will do, knowing they predict too
+//!
+//! Every round each player predicts what percentage of players will choose
+//! side 0, then picks a side, and scores by how close the prediction came
+//! ([`PREDICTION_POINTS`] for an exact one). Ending up on the minority side
+//! scores [`MINORITY_POINTS`](crate::rules::MINORITY_POINTS) as in the
+//! minority game, so a prediction everybody believes undoes itself.
+//!
+//! Seeing someone's prediction before choosing would change the choice, so
+//! a round runs in three phases with a deadline each:
+//!
+//! 1. [`Commit`](OraclePhase::Commit): players send the [`commitment`] to
+//!    their prediction, a hash of the prediction and a nonce of their own.
+//! 2. [`Act`](OraclePhase::Act): players choose sides.
+//! 3. [`Reveal`](OraclePhase::Reveal): players send the prediction and
+//!    nonce, which must hash to their commitment.
+//!
+//! Players who committed or chose a side but did not reveal forfeit the
+//! round and score nothing; their side still counts.
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fmt::Write;
+
+/// Points for predicting the share of side 0 exactly; each percentage
+/// point off costs 1% of them
+pub const PREDICTION_POINTS: i32 = 20;
+
+/// Longest accepted nonce, in characters
+pub const MAX_NONCE_CHARS: usize = 64;
+
+/// Phases of an oracle paradox round, in order
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OraclePhase {
+    #[default]
+    Commit,
+    Act,
+    Reveal,
+}
+
+impl OraclePhase {
+    /// How long the round engine leaves players for the phase
+    pub fn duration_ms(self) -> u64 {
+        match self {
+            OraclePhase::Commit => 2000,
+            OraclePhase::Act => 2000,
+            OraclePhase::Reveal => 1500,
+        }
+    }
+
+    /// The phase after this one, or `None` once the round is over
+    pub fn next(self) -> Option<Self> {
+        match self {
+            OraclePhase::Commit => Some(OraclePhase::Act),
+            OraclePhase::Act => Some(OraclePhase::Reveal),
+            OraclePhase::Reveal => None,
+        }
+    }
+
+    /// Deadline of the phase when it starts at `now`
+    pub fn deadline(self, now: DateTime<Utc>) -> DateTime<Utc> {
+        now + Duration::milliseconds(self.duration_ms() as i64)
+    }
+}
+
+/// Oracle paradox bookkeeping everyone may see
+///
+/// Commitments are public from the start, predictions only once revealed.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct OracleState {
+    pub phase: OraclePhase,
+    /// When the round engine ends the current phase; `None` before the game starts
+    pub deadline: Option<DateTime<Utc>>,
+    /// Commitments so far this round, by player
+    pub commitments: HashMap<String, String>,
+    /// Sides chosen so far this round, by player
+    pub choices: HashMap<String, u8>,
+    /// Verified predictions so far this round, by player
+    pub reveals: HashMap<String, u8>,
+    /// Every finished round
+    pub rounds: Vec<OracleRound>,
+}
+
+impl OracleState {
+    /// Begin a round's commit phase at `now`
+    pub fn start_round(&mut self, now: DateTime<Utc>) {
+        self.clear_round();
+        self.deadline = Some(OraclePhase::Commit.deadline(now));
+    }
+
+    /// Forget the round in progress and go back to its commit phase,
+    /// keeping the deadline
+    pub fn clear_round(&mut self) {
+        self.phase = OraclePhase::Commit;
+        self.commitments.clear();
+        self.choices.clear();
+        self.reveals.clear();
+    }
+
+    /// Forget a player's part in the round in progress
+    pub fn remove_player(&mut self, player_id: &str) {
+        self.commitments.remove(player_id);
+        self.choices.remove(player_id);
+        self.reveals.remove(player_id);
+    }
+
+    /// How behaviour moved after each round's predictions were revealed,
+    /// for rounds followed by another in which someone chose a side
+    pub fn paradox(&self) -> Vec<ParadoxStep> {
+        self.rounds.windows(2)
+            .filter_map(|pair| {
+                let (round, next) = (&pair[0], &pair[1]);
+                let (consensus, share, next_share) = (round.consensus?, round.share?, next.share?);
+                Some(ParadoxStep {
+                    round: round.round,
+                    consensus,
+                    share,
+                    next_share,
+                    divergence: (next_share - consensus).abs() - (share - consensus).abs(),
+                })
+            })
+            .collect()
+    }
+}
+
+/// A finished round
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OracleRound {
+    pub round: u32,
+    /// Percentage of the players who chose a side that chose side 0;
+    /// `None` if nobody did
+    pub share: Option<f32>,
+    /// Mean of the revealed predictions; `None` if nobody revealed
+    pub consensus: Option<f32>,
+    /// The side fewer players chose, ties going to side 0
+    pub minority: Option<u8>,
+    /// Every player who took part, by player id
+    pub scores: Vec<PredictionScore>,
+}
+
+/// How one player did in a round
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PredictionScore {
+    pub player_id: String,
+    /// Revealed prediction
+    pub prediction: Option<u8>,
+    pub side: Option<u8>,
+    pub points: i32,
+    /// Committed or chose a side without revealing, scoring nothing
+    pub forfeited: bool,
+}
+
+/// What the revealed predictions of one round did to the next
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ParadoxStep {
+    pub round: u32,
+    pub consensus: f32,
+    pub share: f32,
+    /// Share of side 0 in the round after
+    pub next_share: f32,
+    /// How many percentage points further from the consensus behaviour
+    /// moved once it was revealed; positive when the predictions defeated
+    /// themselves
+    pub divergence: f32,
+}
+
+/// Commitment to `prediction`: the hex SHA-256 of `"{prediction}:{nonce}"`
+pub fn commitment(prediction: u8, nonce: &str) -> String {
+    let digest = Sha256::digest(format!("{}:{}", prediction, nonce).as_bytes());
+    digest.iter().fold(String::with_capacity(64), |mut hex, byte| {
+        let _ = write!(hex, "{:02x}", byte);
+        hex
+    })
+}
+
+/// Check the form of a commitment
+pub fn validate_commitment(commitment: &str) -> Result<(), String> {
+    if commitment.len() != 64 || !commitment.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f')) {
+        return Err("Commitments are 64 lowercase hex digits".to_string());
+    }
+    Ok(())
+}
+
+/// Check a reveal against the commitment it opens
+pub fn verify_reveal(commitment: &str, prediction: u8, nonce: &str) -> Result<(), String> {
+    if prediction > 100 {
+        return Err("Predictions are percentages from 0 to 100".to_string());
+    }
+    if nonce.is_empty() || nonce.chars().count() > MAX_NONCE_CHARS {
+        return Err(format!("Nonces are 1 to {} characters", MAX_NONCE_CHARS));
+    }
+    if self::commitment(prediction, nonce) != commitment {
+        return Err("Reveal does not match your commitment".to_string());
+    }
+    Ok(())
+}
+
+/// Points for predicting `prediction` when `share` percent chose side 0
+pub fn prediction_points(prediction: u8, share: f32) -> i32 {
+    let miss = (prediction as f32 - share).abs().min(100.0);
+    (PREDICTION_POINTS as f32 * (100.0 - miss) / 100.0).round() as i32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analytics::GameAnalytics;
+    use crate::rules::{self, MINORITY_POINTS};
+    use crate::{new_game, GameAction, GameState, GameStatus, GameType, Player, PlayerType};
+
+    const PLAYERS: [&str; 3] = ["ada", "bo", "cy"];
+
+    fn game(max_rounds: u32) -> GameState {
+        let mut state = new_game(GameType::OracleParadox, max_rounds, Some(1));
+        for id in PLAYERS {
+            rules::add_player(&mut state, Player {
+                id: id.to_string(),
+                name: id.to_string(),
+                player_type: PlayerType::SingleAI { model: "human".to_string() },
+                score: 0,
+                neurons_placed: 0,
+                color: "#00ffff".to_string(),
+            });
+        }
+        rules::start_game(&mut state).unwrap();
+        state
+    }
+
+    fn nonce(player_id: &str, round: u32) -> String {
+        format!("{}-{}", player_id, round)
+    }
+
+    /// Play one round: `moves` gives each player's prediction and side, and
+    /// whether they reveal
+    fn play_round(state: &mut GameState, moves: [(u8, u8, bool); 3]) {
+        let round = state.round;
+        for (id, (prediction, _, _)) in PLAYERS.iter().zip(moves) {
+            let commitment = commitment(prediction, &nonce(id, round));
+            rules::apply_action(state, id, GameAction::Commit { commitment }).unwrap();
+        }
+        rules::end_phase(state);
+        for (id, (_, side, _)) in PLAYERS.iter().zip(moves) {
+            rules::apply_action(state, id, GameAction::ChooseSide { side }).unwrap();
+        }
+        rules::end_phase(state);
+        for (id, (prediction, _, reveal)) in PLAYERS.iter().zip(moves) {
+            if reveal {
+                let nonce = nonce(id, round);
+                rules::apply_action(state, id, GameAction::Reveal { prediction, nonce }).unwrap();
+            }
+        }
+        rules::end_phase(state);
+    }
+
+    fn scores(state: &GameState) -> [i32; 3] {
+        PLAYERS.map(|id| state.players[id].score)
+    }
+
+    #[test]
+    fn test_commitment_is_sha256_of_prediction_and_nonce() {
+        // echo -n "42:salt" | sha256sum
+        assert_eq!(commitment(42, "salt"), "cecb21329daea6121e0471c52be8ea1d59028c2daf2efff4a59fefe03581c229");
+        assert!(validate_commitment(&commitment(42, "salt")).is_ok());
+        assert!(validate_commitment(&commitment(42, "salt").to_uppercase()).is_err());
+        assert!(validate_commitment("42").is_err());
+    }
+
+    #[test]
+    fn test_tampered_reveals_are_rejected() {
+        let mut state = game(3);
+        let commitment = commitment(40, "secret");
+        rules::apply_action(&mut state, "ada", GameAction::Commit { commitment: commitment.clone() }).unwrap();
+        let reveal = |prediction: u8, nonce: &str| GameAction::Reveal { prediction, nonce: nonce.to_string() };
+        assert!(rules::apply_action(&mut state, "ada", reveal(40, "secret")).is_err(), "reveals wait for the reveal phase");
+
+        rules::end_phase(&mut state);
+        rules::apply_action(&mut state, "ada", GameAction::ChooseSide { side: 1 }).unwrap();
+        rules::end_phase(&mut state);
+        assert_eq!(state.oracle.phase, OraclePhase::Reveal);
+
+        for (prediction, nonce) in [(41, "secret"), (40, "Secret"), (40, "secret ")] {
+            let err = rules::apply_action(&mut state, "ada", reveal(prediction, nonce)).unwrap_err();
+            assert_eq!(err, "Reveal does not match your commitment");
+        }
+        assert!(rules::apply_action(&mut state, "bo", reveal(40, "secret")).is_err(), "bo never committed");
+        assert!(state.oracle.reveals.is_empty());
+
+        rules::apply_action(&mut state, "ada", reveal(40, "secret")).unwrap();
+        assert_eq!(state.oracle.reveals["ada"], 40);
+        assert!(rules::apply_action(&mut state, "ada", reveal(40, "secret")).is_err(), "one reveal per round");
+        assert!(verify_reveal(&commitment, 40, "secret").is_ok());
+    }
+
+    #[test]
+    fn test_phases_gate_actions() {
+        let mut state = game(3);
+        assert_eq!(state.oracle.phase, OraclePhase::Commit);
+        assert!(state.oracle.deadline.is_some());
+        assert!(rules::apply_action(&mut state, "ada", GameAction::ChooseSide { side: 0 }).is_err());
+        assert!(rules::apply_action(&mut state, "ada", GameAction::Commit { commitment: "not a hash".to_string() }).is_err());
+        assert!(rules::apply_action(&mut state, "ada", GameAction::PlaceNeuron { x: 0, y: 0 }).is_err());
+
+        let committed = commitment(50, "n");
+        rules::apply_action(&mut state, "ada", GameAction::Commit { commitment: committed.clone() }).unwrap();
+        assert!(rules::apply_action(&mut state, "ada", GameAction::Commit { commitment: committed }).is_err());
+
+        let now = Utc::now();
+        rules::end_phase_at(&mut state, now);
+        assert_eq!((state.oracle.phase, state.round), (OraclePhase::Act, 0));
+        assert_eq!(state.oracle.deadline, Some(now + Duration::milliseconds(2000)));
+        assert!(rules::apply_action(&mut state, "bo", GameAction::Commit { commitment: commitment(50, "m") }).is_err());
+        assert_eq!(rules::legal_actions(&state, "bo").len(), 2);
+
+        rules::end_phase(&mut state);
+        rules::end_phase(&mut state);
+        assert_eq!((state.oracle.phase, state.round), (OraclePhase::Commit, 1));
+        // ada committed without revealing
+        let round = &state.oracle.rounds[0];
+        assert_eq!(round.scores.len(), 1);
+        assert!(round.scores[0].forfeited);
+    }
+
+    #[test]
+    fn test_scripted_match_scores_deterministically() {
+        let script = [
+            // Two of three choose side 0: side 1 is the minority
+            [(67, 0, true), (50, 0, true), (30, 1, true)],
+            // cy does not reveal and forfeits; side 0 is the minority
+            [(80, 1, true), (33, 0, true), (0, 1, false)],
+            // Everyone on side 1, so nobody is in the minority
+            [(100, 1, true), (0, 1, true), (20, 1, true)],
+        ];
+        let play = || {
+            let mut state = game(3);
+            for moves in script {
+                play_round(&mut state, moves);
+            }
+            state
+        };
+
+        let state = play();
+        assert_eq!(state.status, GameStatus::Finished);
+        // Round 0: share 66.7; ada misses by 0.3, bo by 16.7, cy by 36.7
+        //   prediction points 20, 17, 13; cy also scores the minority side
+        // Round 1: share 33.3; ada misses by 46.7, bo by 0.3, cy forfeits
+        //   prediction points 11, 20; bo also scores the minority side
+        // Round 2: share 0; prediction points 0, 20, 16
+        assert_eq!(scores(&state), [20 + 11, 17 + 20 + MINORITY_POINTS + 20, 13 + MINORITY_POINTS + 16]);
+        assert_eq!(state.winner.as_deref(), Some("bo"));
+
+        let forfeit = &state.oracle.rounds[1].scores[2];
+        assert_eq!((forfeit.player_id.as_str(), forfeit.points, forfeit.forfeited), ("cy", 0, true));
+        assert_eq!(forfeit.side, Some(1));
+        assert_eq!(state.oracle.rounds[1].minority, Some(0));
+        assert_eq!(state.oracle.rounds[2].minority, Some(0));
+
+        // Round 0 predicted 49 and behaviour moved from 66.7 to 33.3, two
+        // points closer; round 1 predicted 56.5 and behaviour fled to 0
+        let paradox = state.oracle.paradox();
+        assert_eq!(paradox.len(), 2);
+        assert!((paradox[0].consensus - 49.0).abs() < 1e-4);
+        assert!((paradox[0].divergence - (15.6667 - 17.6667)).abs() < 1e-3);
+        assert!((paradox[1].divergence - (56.5 - 23.1667)).abs() < 1e-3);
+
+        let analytics = GameAnalytics::of(&state).oracle.unwrap();
+        assert_eq!(analytics.forfeits["cy"], 1);
+        assert_eq!(analytics.predictions["ada"].iter().map(|p| p.points).collect::<Vec<_>>(), vec![20, 11, 0]);
+        assert!(analytics.paradox_index.unwrap() > 0.0);
+
+        assert_eq!(scores(&play()), scores(&state));
+    }
+}
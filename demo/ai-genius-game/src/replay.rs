@@ -0,0 +1,331 @@
+//! Match recording and deterministic playback
+//!
+//! [`rules`](crate::rules) appends every join, start, accepted action and
+//! round end to the game's log. The rules themselves are deterministic and
+//! anything random draws from the game's seeded RNG, so the seed and the log
+//! are enough to rebuild the game as it was at any round. Moderation
+//! (removed players, pauses, annulled rounds and flags) is logged as well.
+//!
+//! Knowing the seed means knowing what the RNG will draw, so it is left out
+//! of the game state sent to clients and revealed once the game is finished.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{new_game, rules, GameAction, GameState, GameStatus, GameType, Player};
+
+/// A recorded state change
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum MatchEvent {
+    PlayerJoined { player: Player },
+    GameStarted,
+    Action { player_id: String, action: GameAction },
+    /// A phase of an oracle paradox round other than the last ended
+    PhaseEnded,
+    RoundEnded,
+    PlayerRemoved { player_id: String, reason: String },
+    GamePaused,
+    GameResumed,
+    RoundAnnulled,
+    PlayerFlagged { player_id: String, reason: String },
+}
+
+/// One entry of the match log
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogEntry {
+    /// Round the change happened in
+    pub round: u32,
+    pub timestamp: DateTime<Utc>,
+    #[serde(flatten)]
+    pub event: MatchEvent,
+}
+
+/// Everything needed to rebuild a game
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MatchLog {
+    pub game_id: String,
+    pub game_type: GameType,
+    pub max_rounds: u32,
+    pub seed: u64,
+    pub entries: Vec<LogEntry>,
+}
+
+impl MatchLog {
+    /// The log recorded so far for `state`
+    pub fn of(state: &GameState) -> Self {
+        Self {
+            game_id: state.id.clone(),
+            game_type: state.game_type.clone(),
+            max_rounds: state.max_rounds,
+            seed: state.rules.seed(),
+            entries: state.rules.log().to_vec(),
+        }
+    }
+
+    /// The log of `state` if it is finished. Until then it is withheld from
+    /// clients, since its seed foretells the rest of the match.
+    pub fn of_finished(state: &GameState) -> Option<Self> {
+        (state.status == GameStatus::Finished).then(|| Self::of(state))
+    }
+}
+
+/// Steps through a match log one entry at a time
+pub struct Playback {
+    entries: std::vec::IntoIter<LogEntry>,
+    next: Option<LogEntry>,
+    state: GameState,
+}
+
+impl Playback {
+    /// Start from the game as it was created, before anyone joined
+    pub fn new(log: MatchLog) -> Self {
+        let mut state = new_game(log.game_type, log.max_rounds, Some(log.seed));
+        state.id = log.game_id;
+        let mut entries = log.entries.into_iter();
+        let next = entries.next();
+        Self { entries, next, state }
+    }
+
+    /// The game after every entry applied so far
+    pub fn state(&self) -> &GameState {
+        &self.state
+    }
+
+    /// The entry [`step`](Self::step) applies next
+    pub fn peek(&self) -> Option<&LogEntry> {
+        self.next.as_ref()
+    }
+
+    /// Apply the next entry; `false` once the log is exhausted
+    pub fn step(&mut self) -> Result<bool, String> {
+        let entry = match self.next.take() {
+            Some(entry) => entry,
+            None => return Ok(false),
+        };
+        self.next = self.entries.next();
+
+        let state = &mut self.state;
+        match entry.event {
+            MatchEvent::PlayerJoined { player } => rules::add_player_at(state, player, entry.timestamp),
+            MatchEvent::GameStarted => rules::start_game_at(state, entry.timestamp)?,
+            MatchEvent::Action { player_id, action } => {
+                rules::apply_action_at(state, &player_id, action, entry.timestamp)
+                    .map_err(|e| format!("Round {} action by {} does not replay: {}", entry.round, player_id, e))?
+            }
+            MatchEvent::PhaseEnded => rules::end_phase_at(state, entry.timestamp),
+            MatchEvent::RoundEnded => rules::end_round_at(state, entry.timestamp),
+            MatchEvent::PlayerRemoved { player_id, reason } => {
+                rules::remove_player_at(state, &player_id, &reason, entry.timestamp)?;
+            }
+            MatchEvent::GamePaused => rules::pause_game_at(state, entry.timestamp)?,
+            MatchEvent::GameResumed => rules::resume_game_at(state, entry.timestamp)?,
+            MatchEvent::RoundAnnulled => rules::annul_round_at(state, entry.timestamp)?,
+            MatchEvent::PlayerFlagged { player_id, reason } => {
+                rules::flag_player_at(state, &player_id, &reason, entry.timestamp)
+            }
+        }
+        Ok(true)
+    }
+
+    /// Apply every remaining entry
+    pub fn finish(mut self) -> Result<GameState, String> {
+        while self.step()? {}
+        Ok(self.state)
+    }
+}
+
+/// The game as it was at the start of `round`
+pub fn state_at_round(log: MatchLog, round: u32) -> Result<GameState, String> {
+    let mut playback = Playback::new(log);
+    while playback.peek().is_some_and(|entry| entry.round < round) {
+        playback.step()?;
+    }
+    Ok(playback.state)
+}
+
+/// The game after the last recorded change
+pub fn final_state(log: MatchLog) -> Result<GameState, String> {
+    Playback::new(log).finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bots::{create_bot, Bot, BotKind};
+    use crate::maze::Direction;
+    use crate::oracle;
+    use crate::{GameView, PlayerType};
+    use rand::Rng;
+
+    /// A game played to the end by bots and one scripted player, with the
+    /// serialized state at the start of every round
+    fn play(game_type: GameType, kind: BotKind, seed: u64) -> (GameState, Vec<serde_json::Value>) {
+        let mut state = new_game(game_type, 12, Some(seed));
+        let mut bots: Vec<(String, Box<dyn Bot>)> = Vec::new();
+        for i in 0..3 {
+            let id = format!("bot-{}-{}", kind.as_str(), i);
+            rules::add_player(&mut state, Player {
+                id: id.clone(),
+                name: id.clone(),
+                player_type: PlayerType::Bot { strategy: kind },
+                score: 0,
+                neurons_placed: 0,
+                color: "#ffffff".to_string(),
+            });
+            bots.push((id, create_bot(kind, state.rules.rng().gen(), Some(1_000))));
+        }
+        rules::add_player(&mut state, Player {
+            id: "human".to_string(),
+            name: "human".to_string(),
+            player_type: PlayerType::SingleAI { model: "human".to_string() },
+            score: 0,
+            neurons_placed: 0,
+            color: "#00ffff".to_string(),
+        });
+        rules::start_game(&mut state).unwrap();
+
+        let mut rounds = vec![serde_json::Value::Null];
+        while state.status == GameStatus::Running {
+            for (_, bot) in bots.iter_mut() {
+                bot.observe(&state);
+            }
+            for (id, bot) in bots.iter_mut() {
+                if let Some(action) = bot.choose_action(&state, id) {
+                    rules::apply_action(&mut state, id, action).unwrap();
+                }
+            }
+            // Rejected actions must not be recorded
+            let side = (state.round % 3) as u8;
+            let _ = rules::apply_action(&mut state, "human", GameAction::ChooseSide { side });
+            let _ = rules::apply_action(&mut state, "human", GameAction::PlaceNeuron { x: 0, y: 0 });
+            let _ = rules::apply_action(&mut state, "human", GameAction::SubmitWord { word: "Star light".to_string() });
+            let nonce = format!("human-{}", state.round);
+            let commitment = oracle::commitment(50, &nonce);
+            let _ = rules::apply_action(&mut state, "human", GameAction::Commit { commitment });
+            let _ = rules::apply_action(&mut state, "human", GameAction::Reveal { prediction: 50, nonce });
+            let direction = Direction::ALL[state.round as usize % 4];
+            let _ = rules::apply_action(&mut state, "human", GameAction::Move { direction });
+            let text = format!("round {}", state.round);
+            let _ = rules::apply_action(&mut state, "human", GameAction::SendMessage { to: None, text });
+
+            let round = state.round;
+            rules::end_phase(&mut state);
+            if state.status == GameStatus::Running && state.round != round {
+                rounds.push(serde_json::to_value(&state).unwrap());
+            }
+        }
+        (state, rounds)
+    }
+
+    /// The log as a client would receive it from the replay endpoint
+    fn log_of(state: &GameState) -> MatchLog {
+        let json = serde_json::to_string(&MatchLog::of(state)).unwrap();
+        serde_json::from_str(&json).unwrap()
+    }
+
+    #[test]
+    fn test_reconstructed_final_state_matches_recording() {
+        for game_type in [
+            GameType::ConsciousnessEmergence,
+            GameType::MinorityGame,
+            GameType::SemanticShapeshifter,
+            GameType::OracleParadox,
+            GameType::CollectiveMaze,
+        ] {
+            for (kind, seed) in [(BotKind::Random, 1), (BotKind::Greedy, 2), (BotKind::TitForTat, 3), (BotKind::MonteCarlo, 4)] {
+                let (recorded, _) = play(game_type.clone(), kind, seed);
+                let rebuilt = final_state(log_of(&recorded)).unwrap();
+
+                assert_eq!(recorded.status, GameStatus::Finished);
+                assert_eq!(
+                    serde_json::to_value(&rebuilt).unwrap(),
+                    serde_json::to_value(&recorded).unwrap(),
+                    "{:?} with {} bots", game_type, kind.as_str(),
+                );
+                assert_eq!(rebuilt.rules.log().len(), recorded.rules.log().len());
+            }
+        }
+    }
+
+    #[test]
+    fn test_state_at_every_round() {
+        for game_type in [
+            GameType::ConsciousnessEmergence,
+            GameType::MinorityGame,
+            GameType::SemanticShapeshifter,
+            GameType::OracleParadox,
+            GameType::CollectiveMaze,
+        ] {
+            let (recorded, rounds) = play(game_type, BotKind::Random, 9);
+            let log = log_of(&recorded);
+            assert!(rounds.len() > 1);
+
+            for (round, expected) in rounds.iter().enumerate().skip(1) {
+                let rebuilt = state_at_round(log.clone(), round as u32).unwrap();
+                assert_eq!(rebuilt.round, round as u32);
+                assert_eq!(&serde_json::to_value(&rebuilt).unwrap(), expected, "round {}", round);
+            }
+        }
+    }
+
+    #[test]
+    fn test_log_records_seed_and_accepted_actions_only() {
+        let (recorded, _) = play(GameType::MinorityGame, BotKind::Greedy, 5);
+        let log = MatchLog::of(&recorded);
+
+        assert_eq!(log.seed, 5);
+        assert_eq!(recorded.seed, Some(5));
+        let actions = log.entries.iter()
+            .filter(|e| matches!(e.event, MatchEvent::Action { .. }))
+            .count();
+        assert_eq!(actions, recorded.decisions.len());
+        let rounds_ended = log.entries.iter()
+            .filter(|e| matches!(e.event, MatchEvent::RoundEnded))
+            .count();
+        assert_eq!(rounds_ended as u32, recorded.round);
+    }
+
+    #[test]
+    fn test_seed_is_revealed_once_the_game_is_finished() {
+        let mut state = new_game(GameType::MinorityGame, 5, Some(5));
+        rules::start_game(&mut state).unwrap();
+        assert!(serde_json::to_value(&state).unwrap().get("seed").is_none());
+        assert_eq!(serde_json::to_value(GameView::of(&state)).unwrap()["seed"], serde_json::Value::Null);
+
+        let (recorded, _) = play(GameType::MinorityGame, BotKind::Greedy, 5);
+        assert!(serde_json::to_value(&recorded).unwrap().get("seed").is_none());
+        assert_eq!(serde_json::to_value(GameView::of(&recorded)).unwrap()["seed"], 5);
+    }
+
+    #[test]
+    fn test_log_is_withheld_until_the_game_is_finished() {
+        let mut state = new_game(GameType::SemanticShapeshifter, 5, Some(6));
+        rules::start_game(&mut state).unwrap();
+        assert!(MatchLog::of_finished(&state).is_none());
+
+        let (recorded, _) = play(GameType::SemanticShapeshifter, BotKind::Random, 6);
+        assert_eq!(MatchLog::of_finished(&recorded).unwrap().seed, 6);
+    }
+
+    #[test]
+    fn test_tampered_log_is_rejected() {
+        let (recorded, _) = play(GameType::ConsciousnessEmergence, BotKind::Random, 11);
+        let mut log = MatchLog::of(&recorded);
+        let index = log.entries.iter()
+            .position(|e| matches!(e.event, MatchEvent::Action { .. }))
+            .unwrap();
+        log.entries.insert(index + 1, log.entries[index].clone());
+
+        assert!(final_state(log).is_err());
+    }
+
+    #[test]
+    fn test_simulations_do_not_record() {
+        let mut state = new_game(GameType::MinorityGame, 5, Some(3));
+        state.rules = state.rules.simulation();
+        rules::start_game(&mut state).unwrap();
+        rules::end_round(&mut state);
+        assert!(state.rules.log().is_empty());
+    }
+}
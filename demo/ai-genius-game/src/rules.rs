@@ -0,0 +1,879 @@
+//! Game rules shared by every kind of player
+//!
+//! Human, AI and bot actions all go through [`validate_action`] and
+//! [`apply_action`], and every accepted action is recorded as a
+//! [`DecisionRecord`] so analytics can treat all players uniformly.
+//!
+//! Every state change made here is also appended to the game's match log
+//! (see [`replay`](crate::replay)), which is enough to rebuild the game.
+//! The `*_at` variants take the timestamp explicitly so replays reproduce
+//! the original events exactly.
+//!
+//! The semantic shapeshifter's hidden target lives here too, server-side,
+//! with the rest of the rules state; see [`shapeshifter`](crate::shapeshifter).
+//!
+//! The collective maze lives in the game state whole, and is redacted per
+//! player on the way out; see [`maze`](crate::maze).
+//!
+//! Oracle paradox rounds run in phases, each ended by [`end_phase`] at its
+//! deadline; see [`oracle`](crate::oracle). For every other game a round is
+//! a single phase and [`end_phase`] is [`end_round`].
+//!
+//! Moderation goes through here too: removing players, pausing, annulling
+//! the round in progress and flagging suspicious play are all logged, so a
+//! moderated match replays as it happened.
+
+use chrono::{DateTime, Utc};
+use rand::{rngs::StdRng, SeedableRng};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::maze::{self, Direction, GoalReached, MazeMessage, MazeState};
+use crate::oracle::{self, OraclePhase, OracleRound, PredictionScore};
+use crate::replay::{LogEntry, MatchEvent};
+use crate::shapeshifter::{self, Shapeshifter, ShapeshifterRound, SubmissionScore, Vocabulary};
+use crate::{
+    board::Board, calculate_consciousness, consciousness_from_totals, Cell, DecisionRecord,
+    GameAction, GameEvent, GameState, GameStatus, GameType, Player, CONSCIOUSNESS_THRESHOLD,
+    MAX_NEURONS_PER_PLAYER,
+};
+
+/// Number of sides in the minority game
+pub const MINORITY_SIDES: u8 = 2;
+
+/// Points for an accepted neuron placement
+pub const PLACE_NEURON_POINTS: i32 = 10;
+
+/// Points for strengthening a connection
+pub const STRENGTHEN_POINTS: i32 = 5;
+
+/// Points for ending a round on the minority side
+pub const MINORITY_POINTS: i32 = 10;
+
+/// Per-game rules state: the seeded RNG and the match log
+///
+/// Anything random about a game must draw from [`rng`](Self::rng), or like
+/// the shapeshifter's target from a stream of its own derived from the
+/// seed, so that the seed and the log together determine the whole match.
+#[derive(Debug, Clone)]
+pub struct GameRules {
+    seed: u64,
+    rng: StdRng,
+    shapeshifter: Shapeshifter,
+    /// `None` for simulations, which are never replayed
+    log: Option<Vec<LogEntry>>,
+    /// The game as the current round started, for annulling it; `None`
+    /// before the game starts and in simulations
+    checkpoint: Option<RoundCheckpoint>,
+}
+
+/// What annulling a round restores
+#[derive(Debug, Clone)]
+struct RoundCheckpoint {
+    board: Board,
+    players: HashMap<String, Player>,
+    consciousness_level: f32,
+    maze: MazeState,
+}
+
+impl RoundCheckpoint {
+    fn of(state: &GameState) -> Self {
+        Self {
+            board: state.board.clone(),
+            players: state.players.clone(),
+            consciousness_level: state.consciousness_level,
+            maze: state.maze.clone(),
+        }
+    }
+}
+
+impl GameRules {
+    /// Rules for a new game, recording every change
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            rng: StdRng::seed_from_u64(seed),
+            shapeshifter: Shapeshifter::new(Vocabulary::builtin(), seed),
+            log: Some(Vec::new()),
+            checkpoint: None,
+        }
+    }
+
+    /// A copy for throwaway simulations: same RNG state, no recording
+    pub fn simulation(&self) -> Self {
+        Self {
+            seed: self.seed,
+            rng: self.rng.clone(),
+            shapeshifter: self.shapeshifter.simulation(),
+            log: None,
+            checkpoint: None,
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn rng(&mut self) -> &mut StdRng {
+        &mut self.rng
+    }
+
+    pub fn shapeshifter(&self) -> &Shapeshifter {
+        &self.shapeshifter
+    }
+
+    /// Play the shapeshifter over `vocabulary` instead of the built-in one;
+    /// only before the game starts
+    pub fn set_vocabulary(&mut self, vocabulary: Arc<Vocabulary>) {
+        self.shapeshifter = Shapeshifter::new(vocabulary, self.seed);
+    }
+
+    /// Everything recorded so far, oldest first
+    pub fn log(&self) -> &[LogEntry] {
+        self.log.as_deref().unwrap_or_default()
+    }
+
+    fn record(&mut self, round: u32, timestamp: DateTime<Utc>, event: MatchEvent) {
+        if let Some(log) = &mut self.log {
+            log.push(LogEntry { round, timestamp, event });
+        }
+    }
+}
+
+impl Default for GameRules {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+/// Add a player to the game
+pub fn add_player(state: &mut GameState, player: Player) {
+    add_player_at(state, player, Utc::now());
+}
+
+/// [`add_player`] with an explicit timestamp
+pub fn add_player_at(state: &mut GameState, player: Player, now: DateTime<Utc>) {
+    state.events.push(GameEvent {
+        timestamp: now,
+        event_type: "player_joined".to_string(),
+        description: format!("Player {} joined the game", player.name),
+    });
+    state.players.insert(player.id.clone(), player.clone());
+    if let (GameType::CollectiveMaze, GameStatus::Running | GameStatus::Paused) = (&state.game_type, &state.status) {
+        state.maze.add_avatar(&player.id);
+    }
+    let round = state.round;
+    state.rules.record(round, now, MatchEvent::PlayerJoined { player });
+}
+
+/// Start a waiting game
+pub fn start_game(state: &mut GameState) -> Result<(), String> {
+    start_game_at(state, Utc::now())
+}
+
+/// [`start_game`] with an explicit timestamp
+pub fn start_game_at(state: &mut GameState, now: DateTime<Utc>) -> Result<(), String> {
+    if state.status != GameStatus::Waiting {
+        return Err("Game is not waiting to start".to_string());
+    }
+    state.status = GameStatus::Running;
+    if let GameType::SemanticShapeshifter = state.game_type {
+        state.rules.shapeshifter.pick_target();
+    }
+    if let GameType::OracleParadox = state.game_type {
+        state.oracle.start_round(now);
+    }
+    if let GameType::CollectiveMaze = state.game_type {
+        state.maze = MazeState::generate(state.rules.seed());
+        for player_id in state.players.keys() {
+            state.maze.add_avatar(player_id);
+        }
+    }
+    state.events.push(GameEvent {
+        timestamp: now,
+        event_type: "game_started".to_string(),
+        description: format!("Game started with {} players", state.players.len()),
+    });
+    let round = state.round;
+    state.rules.record(round, now, MatchEvent::GameStarted);
+    checkpoint(state);
+    Ok(())
+}
+
+/// Remember the game as the round starts, unless it is a simulation
+fn checkpoint(state: &mut GameState) {
+    if state.rules.log.is_some() {
+        state.rules.checkpoint = Some(RoundCheckpoint::of(state));
+    }
+}
+
+/// Remove a player from the game; `reason` completes "Player X was ..."
+pub fn remove_player(state: &mut GameState, player_id: &str, reason: &str) -> Result<Player, String> {
+    remove_player_at(state, player_id, reason, Utc::now())
+}
+
+/// [`remove_player`] with an explicit timestamp
+pub fn remove_player_at(state: &mut GameState, player_id: &str, reason: &str, now: DateTime<Utc>) -> Result<Player, String> {
+    let player = state.players.remove(player_id)
+        .ok_or_else(|| "Player is not in this game".to_string())?;
+    state.minority.choices.remove(player_id);
+    state.shapeshifter.submissions.remove(player_id);
+    state.oracle.remove_player(player_id);
+    state.maze.remove_player(player_id);
+    state.events.push(GameEvent {
+        timestamp: now,
+        event_type: "player_removed".to_string(),
+        description: format!("Player {} was {}", player.name, reason),
+    });
+    let round = state.round;
+    state.rules.record(round, now, MatchEvent::PlayerRemoved {
+        player_id: player_id.to_string(),
+        reason: reason.to_string(),
+    });
+    Ok(player)
+}
+
+/// Pause a running game; no actions are accepted and the round does not end
+/// until it resumes
+pub fn pause_game(state: &mut GameState) -> Result<(), String> {
+    pause_game_at(state, Utc::now())
+}
+
+/// [`pause_game`] with an explicit timestamp
+pub fn pause_game_at(state: &mut GameState, now: DateTime<Utc>) -> Result<(), String> {
+    if state.status != GameStatus::Running {
+        return Err("Game is not running".to_string());
+    }
+    state.status = GameStatus::Paused;
+    state.events.push(GameEvent {
+        timestamp: now,
+        event_type: "game_paused".to_string(),
+        description: "Game paused by a moderator".to_string(),
+    });
+    let round = state.round;
+    state.rules.record(round, now, MatchEvent::GamePaused);
+    Ok(())
+}
+
+/// Resume a paused game
+pub fn resume_game(state: &mut GameState) -> Result<(), String> {
+    resume_game_at(state, Utc::now())
+}
+
+/// [`resume_game`] with an explicit timestamp
+pub fn resume_game_at(state: &mut GameState, now: DateTime<Utc>) -> Result<(), String> {
+    if state.status != GameStatus::Paused {
+        return Err("Game is not paused".to_string());
+    }
+    state.status = GameStatus::Running;
+    state.events.push(GameEvent {
+        timestamp: now,
+        event_type: "game_resumed".to_string(),
+        description: "Game resumed".to_string(),
+    });
+    let round = state.round;
+    state.rules.record(round, now, MatchEvent::GameResumed);
+    Ok(())
+}
+
+/// Undo every action of the round in progress: the board, scores and
+/// neuron counts go back to how they were when it started, and its
+/// decisions, side choices, word submissions, oracle commitments and
+/// reveals and maze moves and messages are dropped, and an oracle paradox
+/// round goes back to its commit phase. Players who joined or left during
+/// the round stay joined or gone.
+pub fn annul_round(state: &mut GameState) -> Result<(), String> {
+    annul_round_at(state, Utc::now())
+}
+
+/// [`annul_round`] with an explicit timestamp
+pub fn annul_round_at(state: &mut GameState, now: DateTime<Utc>) -> Result<(), String> {
+    if !matches!(state.status, GameStatus::Running | GameStatus::Paused) {
+        return Err("Only a running or paused game has a round to annul".to_string());
+    }
+    let checkpoint = state.rules.checkpoint.clone()
+        .ok_or_else(|| "No round to annul".to_string())?;
+
+    state.board = checkpoint.board;
+    state.consciousness_level = checkpoint.consciousness_level;
+    for player in state.players.values_mut() {
+        // Players who joined this round started from nothing
+        let (score, neurons_placed) = checkpoint.players.get(&player.id)
+            .map(|p| (p.score, p.neurons_placed))
+            .unwrap_or((0, 0));
+        player.score = score;
+        player.neurons_placed = neurons_placed;
+    }
+    state.minority.choices.clear();
+    state.shapeshifter.submissions.clear();
+    state.oracle.clear_round();
+    state.maze.rewind(checkpoint.maze, state.players.keys());
+    let round = state.round;
+    state.decisions.retain(|decision| decision.round != round);
+    state.events.push(GameEvent {
+        timestamp: now,
+        event_type: "round_annulled".to_string(),
+        description: format!("Round {} annulled by a moderator", round),
+    });
+    state.rules.record(round, now, MatchEvent::RoundAnnulled);
+    Ok(())
+}
+
+/// Note suspicious play by a player in the event log
+pub fn flag_player(state: &mut GameState, player_id: &str, reason: &str) {
+    flag_player_at(state, player_id, reason, Utc::now());
+}
+
+/// [`flag_player`] with an explicit timestamp
+pub fn flag_player_at(state: &mut GameState, player_id: &str, reason: &str, now: DateTime<Utc>) {
+    let name = state.players.get(player_id).map_or_else(|| player_id.to_string(), |p| p.name.clone());
+    state.events.push(GameEvent {
+        timestamp: now,
+        event_type: "player_flagged".to_string(),
+        description: format!("Player {} flagged: {}", name, reason),
+    });
+    let round = state.round;
+    state.rules.record(round, now, MatchEvent::PlayerFlagged {
+        player_id: player_id.to_string(),
+        reason: reason.to_string(),
+    });
+}
+
+/// Check whether `player_id` may take `action` in the current round
+pub fn validate_action(state: &GameState, player_id: &str, action: &GameAction) -> Result<(), String> {
+    if state.status != GameStatus::Running {
+        return Err("Game is not running".to_string());
+    }
+    let player = state.players.get(player_id)
+        .ok_or_else(|| "Player is not in this game".to_string())?;
+
+    match (&state.game_type, action) {
+        (GameType::MinorityGame, GameAction::ChooseSide { side }) => {
+            if *side >= MINORITY_SIDES {
+                return Err(format!("Side must be below {}", MINORITY_SIDES));
+            }
+            if state.minority.choices.contains_key(player_id) {
+                return Err("Already chose a side this round".to_string());
+            }
+            Ok(())
+        }
+        (GameType::MinorityGame, _) => Err("Only side choices are allowed in the minority game".to_string()),
+        (GameType::OracleParadox, GameAction::Commit { commitment }) => {
+            if state.oracle.phase != OraclePhase::Commit {
+                return Err("Commitments are only accepted in the commit phase".to_string());
+            }
+            oracle::validate_commitment(commitment)?;
+            if state.oracle.commitments.contains_key(player_id) {
+                return Err("Already committed to a prediction this round".to_string());
+            }
+            Ok(())
+        }
+        (GameType::OracleParadox, GameAction::ChooseSide { side }) => {
+            if state.oracle.phase != OraclePhase::Act {
+                return Err("Sides are only chosen in the act phase".to_string());
+            }
+            if *side >= MINORITY_SIDES {
+                return Err(format!("Side must be below {}", MINORITY_SIDES));
+            }
+            if state.oracle.choices.contains_key(player_id) {
+                return Err("Already chose a side this round".to_string());
+            }
+            Ok(())
+        }
+        (GameType::OracleParadox, GameAction::Reveal { prediction, nonce }) => {
+            if state.oracle.phase != OraclePhase::Reveal {
+                return Err("Predictions are only revealed in the reveal phase".to_string());
+            }
+            let commitment = state.oracle.commitments.get(player_id)
+                .ok_or_else(|| "No commitment to reveal this round".to_string())?;
+            if state.oracle.reveals.contains_key(player_id) {
+                return Err("Already revealed a prediction this round".to_string());
+            }
+            oracle::verify_reveal(commitment, *prediction, nonce)
+        }
+        (GameType::OracleParadox, _) => {
+            Err("Only commitments, side choices and reveals are allowed in the oracle paradox".to_string())
+        }
+        (GameType::CollectiveMaze, GameAction::Move { direction }) => {
+            state.maze.validate_move(player_id, *direction).map(|_| ())
+        }
+        (GameType::CollectiveMaze, GameAction::SendMessage { to, text }) => {
+            if let Some(to) = to {
+                if to == player_id || !state.players.contains_key(to) {
+                    return Err("Messages go to another player in this game, or to everyone".to_string());
+                }
+            }
+            state.maze.validate_message(player_id, text)
+        }
+        (GameType::CollectiveMaze, _) => {
+            Err("Only moves and messages are allowed in the collective maze".to_string())
+        }
+        (_, GameAction::Move { .. } | GameAction::SendMessage { .. }) => {
+            Err("Moves and messages are only allowed in the collective maze".to_string())
+        }
+        (_, GameAction::ChooseSide { .. }) => {
+            Err("Side choices are only allowed in the minority game and the oracle paradox".to_string())
+        }
+        (_, GameAction::Commit { .. } | GameAction::Reveal { .. }) => {
+            Err("Commitments and reveals are only allowed in the oracle paradox".to_string())
+        }
+        (GameType::SemanticShapeshifter, GameAction::SubmitWord { word }) => {
+            shapeshifter::validate_submission(word)?;
+            if state.shapeshifter.submissions.contains_key(player_id) {
+                return Err("Already submitted a word this round".to_string());
+            }
+            Ok(())
+        }
+        (GameType::SemanticShapeshifter, _) => {
+            Err("Only word submissions are allowed in the semantic shapeshifter".to_string())
+        }
+        (_, GameAction::SubmitWord { .. }) => {
+            Err("Word submissions are only allowed in the semantic shapeshifter".to_string())
+        }
+        (_, GameAction::PlaceNeuron { x, y }) => {
+            if !state.board.in_bounds(*x, *y) {
+                return Err("Position out of bounds".to_string());
+            }
+            if state.board.is_occupied(*x, *y) {
+                return Err("Position already occupied".to_string());
+            }
+            if player.neurons_placed as usize >= MAX_NEURONS_PER_PLAYER {
+                return Err("Neuron limit reached".to_string());
+            }
+            Ok(())
+        }
+        (_, GameAction::StrengthenConnection { from, to }) => {
+            if !state.board.in_bounds(from.0, from.1) || !state.board.in_bounds(to.0, to.1) {
+                return Err("Position out of bounds".to_string());
+            }
+            if from == to {
+                return Err("Cannot connect a neuron to itself".to_string());
+            }
+            let from_cell = match state.board.get(from.0, from.1) {
+                Some(cell) if cell.owner.as_deref() == Some(player_id) => cell,
+                _ => return Err("Connections must start at your own neuron".to_string()),
+            };
+            if !state.board.is_occupied(to.0, to.1) {
+                return Err("Connections must end at a neuron".to_string());
+            }
+            if from_cell.connections.contains(to) {
+                return Err("Connection already exists".to_string());
+            }
+            Ok(())
+        }
+        (_, GameAction::ActivateSpecial { ability }) => {
+            if ability.is_empty() {
+                return Err("Ability name is required".to_string());
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Validate and apply an action, recording the decision
+pub fn apply_action(state: &mut GameState, player_id: &str, action: GameAction) -> Result<(), String> {
+    apply_action_at(state, player_id, action, Utc::now())
+}
+
+/// [`apply_action`] with an explicit timestamp
+pub fn apply_action_at(state: &mut GameState, player_id: &str, action: GameAction, now: DateTime<Utc>) -> Result<(), String> {
+    let action = match action {
+        GameAction::SubmitWord { word } => GameAction::SubmitWord { word: shapeshifter::normalize_submission(&word) },
+        action => action,
+    };
+    validate_action(state, player_id, &action)?;
+
+    let description = match &action {
+        GameAction::PlaceNeuron { x, y } => {
+            state.board.set(*x, *y, Cell {
+                owner: Some(player_id.to_string()),
+                neuron_strength: 1.0,
+                connections: vec![],
+            });
+            if let Some(player) = state.players.get_mut(player_id) {
+                player.neurons_placed += 1;
+                player.score += PLACE_NEURON_POINTS;
+            }
+            state.consciousness_level = calculate_consciousness(&state.board);
+            Some(("neuron_placed", format!("Neuron placed at ({}, {})", x, y)))
+        }
+        GameAction::StrengthenConnection { from, to } => {
+            if let Some(cell) = state.board.get_mut(from.0, from.1) {
+                cell.connections.push(*to);
+            }
+            if let Some(player) = state.players.get_mut(player_id) {
+                player.score += STRENGTHEN_POINTS;
+            }
+            state.consciousness_level = calculate_consciousness(&state.board);
+            Some(("connection_strengthened", format!("Connection {:?} -> {:?}", from, to)))
+        }
+        GameAction::ChooseSide { side } => {
+            match state.game_type {
+                GameType::OracleParadox => state.oracle.choices.insert(player_id.to_string(), *side),
+                _ => state.minority.choices.insert(player_id.to_string(), *side),
+            };
+            None
+        }
+        GameAction::Commit { commitment } => {
+            state.oracle.commitments.insert(player_id.to_string(), commitment.clone());
+            None
+        }
+        GameAction::Reveal { prediction, .. } => {
+            state.oracle.reveals.insert(player_id.to_string(), *prediction);
+            None
+        }
+        GameAction::SubmitWord { word } => {
+            state.shapeshifter.submissions.insert(player_id.to_string(), word.clone());
+            None
+        }
+        GameAction::Move { direction } => move_avatar(state, player_id, *direction),
+        // Messages are private, so they leave no event
+        GameAction::SendMessage { to, text } => {
+            *state.maze.bandwidth.entry(player_id.to_string()).or_default() += text.len();
+            state.maze.messages.push(MazeMessage {
+                round: state.round,
+                from: player_id.to_string(),
+                to: to.clone(),
+                text: text.clone(),
+            });
+            None
+        }
+        GameAction::ActivateSpecial { .. } => None,
+    };
+
+    if let Some((event_type, description)) = description {
+        state.events.push(GameEvent {
+            timestamp: now,
+            event_type: event_type.to_string(),
+            description,
+        });
+    }
+
+    let round = state.round;
+    state.rules.record(round, now, MatchEvent::Action {
+        player_id: player_id.to_string(),
+        action: action.clone(),
+    });
+    state.decisions.push(DecisionRecord {
+        round,
+        player_id: player_id.to_string(),
+        action,
+        timestamp: now,
+    });
+
+    if state.consciousness_level >= CONSCIOUSNESS_THRESHOLD {
+        state.status = GameStatus::Finished;
+        state.winner = Some(player_id.to_string());
+    }
+
+    Ok(())
+}
+
+/// End the current phase of the round, and with the last one the round
+pub fn end_phase(state: &mut GameState) {
+    end_phase_at(state, Utc::now());
+}
+
+/// [`end_phase`] with an explicit timestamp
+pub fn end_phase_at(state: &mut GameState, now: DateTime<Utc>) {
+    if state.status != GameStatus::Running {
+        return;
+    }
+    if let (GameType::OracleParadox, Some(next)) = (&state.game_type, state.oracle.phase.next()) {
+        state.oracle.phase = next;
+        state.oracle.deadline = Some(next.deadline(now));
+        let round = state.round;
+        state.rules.record(round, now, MatchEvent::PhaseEnded);
+        return;
+    }
+    end_round_at(state, now);
+}
+
+/// Resolve the current round, whatever phase it is in, and advance to the
+/// next one
+pub fn end_round(state: &mut GameState) {
+    end_round_at(state, Utc::now());
+}
+
+/// [`end_round`] with an explicit timestamp
+pub fn end_round_at(state: &mut GameState, now: DateTime<Utc>) {
+    if state.status != GameStatus::Running {
+        return;
+    }
+    let round = state.round;
+    state.rules.record(round, now, MatchEvent::RoundEnded);
+
+    if let GameType::MinorityGame = state.game_type {
+        let winning = minority_side(state.minority.choices.values());
+        for (player_id, side) in state.minority.choices.drain() {
+            if side == winning {
+                if let Some(player) = state.players.get_mut(&player_id) {
+                    player.score += MINORITY_POINTS;
+                }
+            }
+        }
+        state.minority.history.push(winning);
+    }
+
+    if let GameType::SemanticShapeshifter = state.game_type {
+        resolve_shapeshifter(state, now);
+    }
+
+    if let GameType::OracleParadox = state.game_type {
+        resolve_oracle(state, now);
+    }
+
+    if let GameType::CollectiveMaze = state.game_type {
+        state.maze.end_round(round);
+    }
+
+    state.round += 1;
+    if state.round >= state.max_rounds || state.maze.is_complete() {
+        state.status = GameStatus::Finished;
+        state.oracle.deadline = None;
+        state.winner = state.players.values()
+            .max_by(|a, b| a.score.cmp(&b.score).then_with(|| b.id.cmp(&a.id)))
+            .map(|p| p.id.clone());
+    } else {
+        checkpoint(state);
+    }
+}
+
+/// The side fewer of `sides` are on; ties go to side 0 so every round has a result
+fn minority_side<'a>(sides: impl Iterator<Item = &'a u8>) -> u8 {
+    let mut counts = [0usize; MINORITY_SIDES as usize];
+    for side in sides {
+        counts[*side as usize] += 1;
+    }
+    if counts[1] < counts[0] { 1 } else { 0 }
+}
+
+/// Score this round's revealed predictions against the sides chosen, then
+/// start the next round's commit phase
+///
+/// Everyone who committed or chose a side without revealing forfeits.
+fn resolve_oracle(state: &mut GameState, now: DateTime<Utc>) {
+    let oracle = &state.oracle;
+    let chosen = oracle.choices.len();
+    let share = (chosen > 0).then(|| {
+        let side_0 = oracle.choices.values().filter(|side| **side == 0).count();
+        side_0 as f32 * 100.0 / chosen as f32
+    });
+    let minority = (chosen > 0).then(|| minority_side(oracle.choices.values()));
+    let consensus = (!oracle.reveals.is_empty()).then(|| {
+        oracle.reveals.values().map(|p| *p as f32).sum::<f32>() / oracle.reveals.len() as f32
+    });
+
+    let mut player_ids: Vec<String> = oracle.commitments.keys().chain(oracle.choices.keys()).cloned().collect();
+    player_ids.sort();
+    player_ids.dedup();
+
+    let mut scores = Vec::with_capacity(player_ids.len());
+    for player_id in player_ids {
+        let prediction = state.oracle.reveals.get(&player_id).copied();
+        let side = state.oracle.choices.get(&player_id).copied();
+        let points = match prediction {
+            Some(prediction) => {
+                let accuracy = share.map_or(0, |share| oracle::prediction_points(prediction, share));
+                let minority_bonus = if side.is_some() && side == minority { MINORITY_POINTS } else { 0 };
+                accuracy + minority_bonus
+            }
+            None => 0,
+        };
+        if let Some(player) = state.players.get_mut(&player_id) {
+            player.score += points;
+        }
+        scores.push(PredictionScore { player_id, prediction, side, points, forfeited: prediction.is_none() });
+    }
+
+    if let (Some(share), Some(consensus)) = (share, consensus) {
+        state.events.push(GameEvent {
+            timestamp: now,
+            event_type: "predictions_revealed".to_string(),
+            description: format!(
+                "Round {}: {:.0}% chose side 0, {:.0}% was predicted",
+                state.round, share, consensus
+            ),
+        });
+    }
+    state.oracle.rounds.push(OracleRound { round: state.round, share, consensus, minority, scores });
+    state.oracle.start_round(now);
+}
+
+/// Move a player's avatar, scoring the tile if nobody stood on it before
+/// and the goal if nobody reached it before
+fn move_avatar(state: &mut GameState, player_id: &str, direction: Direction) -> Option<(&'static str, String)> {
+    let to = state.maze.validate_move(player_id, direction).ok()?;
+    state.maze.avatars.insert(player_id.to_string(), to);
+    state.maze.moved.insert(player_id.to_string());
+    let points = maze_points(state, to);
+    if let Some(player) = state.players.get_mut(player_id) {
+        player.score += points;
+    }
+    state.maze.visited.insert(to);
+
+    if state.maze.tile(to) != maze::Tile::Goal || state.maze.is_reached(to) {
+        return None;
+    }
+    state.maze.reached.push(GoalReached { round: state.round, player_id: player_id.to_string(), position: to });
+    let name = state.players.get(player_id).map_or(player_id, |p| p.name.as_str());
+    Some(("goal_reached", format!(
+        "Player {} reached goal {} of {}",
+        name,
+        state.maze.reached.len(),
+        state.maze.goals,
+    )))
+}
+
+/// Points for moving onto `to`
+fn maze_points(state: &GameState, to: (usize, usize)) -> i32 {
+    let mut points = 0;
+    if !state.maze.visited.contains(&to) {
+        points += maze::EXPLORE_POINTS;
+    }
+    if state.maze.tile(to) == maze::Tile::Goal && !state.maze.is_reached(to) {
+        points += maze::goal_points(state.round, state.max_rounds);
+    }
+    points
+}
+
+/// Score this round's submissions against the hidden target, then reveal it
+/// and let it drift
+fn resolve_shapeshifter(state: &mut GameState, now: DateTime<Utc>) {
+    let mut submissions: Vec<(String, String)> = state.shapeshifter.submissions.drain().collect();
+    submissions.sort();
+
+    let mut scores = Vec::with_capacity(submissions.len());
+    for (player_id, word) in submissions {
+        let repeat = state.shapeshifter.submitted_before(&player_id, &word);
+        let similarity = state.rules.shapeshifter.similarity(&word);
+        let points = shapeshifter::points(similarity, repeat);
+        if let Some(player) = state.players.get_mut(&player_id) {
+            player.score += points;
+        }
+        scores.push(SubmissionScore { player_id, word, similarity, points, repeat });
+    }
+
+    let Some((target, drift)) = state.rules.shapeshifter.reveal() else {
+        return;
+    };
+    state.events.push(GameEvent {
+        timestamp: now,
+        event_type: "target_revealed".to_string(),
+        description: format!("The target of round {} was \"{}\"", state.round, target),
+    });
+    state.shapeshifter.rounds.push(ShapeshifterRound { round: state.round, target, drift, scores });
+}
+
+/// Immediate outcome of an action, computed without mutating the game
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Preview {
+    /// Points the acting player would gain this round
+    pub score_delta: i32,
+    /// Consciousness level after the action
+    pub consciousness: f32,
+}
+
+/// Board totals consciousness is derived from: (total strength, connections)
+pub fn board_totals(state: &GameState) -> (f32, usize) {
+    state.board.iter().fold((0.0, 0), |(strength, connections), (_, cell)| {
+        (strength + cell.neuron_strength, connections + cell.connections.len())
+    })
+}
+
+/// Preview `action` against precomputed [`board_totals`], or `None` if it is invalid
+pub fn preview_action(state: &GameState, totals: (f32, usize), player_id: &str, action: &GameAction) -> Option<Preview> {
+    validate_action(state, player_id, action).ok()?;
+
+    let (strength, connections) = totals;
+    let (score_delta, consciousness) = match action {
+        GameAction::PlaceNeuron { .. } => {
+            (PLACE_NEURON_POINTS, consciousness_from_totals(strength + 1.0, connections))
+        }
+        GameAction::StrengthenConnection { .. } => {
+            (STRENGTHEN_POINTS, consciousness_from_totals(strength, connections + 1))
+        }
+        GameAction::ChooseSide { side } => {
+            let choices = match state.game_type {
+                GameType::OracleParadox => &state.oracle.choices,
+                _ => &state.minority.choices,
+            };
+            let same = choices.values().filter(|s| *s == side).count() + 1;
+            let other = choices.len() + 1 - same;
+            let wins = same < other || (same == other && *side == 0);
+            (if wins { MINORITY_POINTS } else { 0 }, state.consciousness_level)
+        }
+        // Scored against the hidden target only when the round ends; the
+        // last revealed target is the best public guess at it
+        GameAction::SubmitWord { word } => {
+            let word = shapeshifter::normalize_submission(word);
+            let repeat = state.shapeshifter.submitted_before(player_id, &word);
+            let similarity = state.rules.shapeshifter.estimate(&word);
+            (shapeshifter::points(similarity, repeat), state.consciousness_level)
+        }
+        // Scored only once the round ends
+        GameAction::Commit { .. } | GameAction::Reveal { .. } => (0, state.consciousness_level),
+        GameAction::Move { direction } => {
+            let to = state.maze.validate_move(player_id, *direction).ok()?;
+            (maze_points(state, to), state.consciousness_level)
+        }
+        GameAction::SendMessage { .. } => (0, state.consciousness_level),
+        GameAction::ActivateSpecial { .. } => (0, state.consciousness_level),
+    };
+
+    Some(Preview { score_delta, consciousness })
+}
+
+/// Every action `player_id` may take in the current round
+pub fn legal_actions(state: &GameState, player_id: &str) -> Vec<GameAction> {
+    let mut candidates = Vec::new();
+
+    match state.game_type {
+        // Commitments and reveals hold a secret only the player knows, so
+        // side choices are all there is to enumerate
+        GameType::MinorityGame | GameType::OracleParadox => {
+            for side in 0..MINORITY_SIDES {
+                candidates.push(GameAction::ChooseSide { side });
+            }
+        }
+        GameType::SemanticShapeshifter => {
+            for word in state.rules.shapeshifter.vocabulary().words() {
+                candidates.push(GameAction::SubmitWord { word: word.clone() });
+            }
+        }
+        // Messages are free text, so moves are all there is to enumerate
+        GameType::CollectiveMaze => {
+            for direction in Direction::ALL {
+                candidates.push(GameAction::Move { direction });
+            }
+        }
+        _ => {
+            let size = state.board.size();
+            for y in 0..size {
+                for x in 0..size {
+                    match state.board.owner(x, y) {
+                        None => candidates.push(GameAction::PlaceNeuron { x, y }),
+                        Some(owner) if owner == player_id => {
+                            for (nx, ny) in neighbours(x, y, size) {
+                                candidates.push(GameAction::StrengthenConnection { from: (x, y), to: (nx, ny) });
+                            }
+                        }
+                        Some(_) => {}
+                    }
+                }
+            }
+        }
+    }
+
+    candidates.retain(|action| validate_action(state, player_id, action).is_ok());
+    candidates
+}
+
+/// Positions adjacent to (x, y), including diagonals
+fn neighbours(x: usize, y: usize, size: usize) -> impl Iterator<Item = (usize, usize)> {
+    (-1i32..=1).flat_map(move |dy| (-1i32..=1).map(move |dx| (dx, dy)))
+        .filter(|&(dx, dy)| dx != 0 || dy != 0)
+        .map(move |(dx, dy)| (x as i32 + dx, y as i32 + dy))
+        .filter(move |&(nx, ny)| nx >= 0 && ny >= 0 && (nx as usize) < size && (ny as usize) < size)
+        .map(|(nx, ny)| (nx as usize, ny as usize))
+}
@@ -0,0 +1,424 @@
+//! Semantic shapeshifter: guess a hidden concept that keeps moving
+//!
+//! The server picks a hidden target word from the [`Vocabulary`]. Every
+//! round each player submits one word or phrase, and when the round ends it
+//! scores by the embedding similarity of the submission to the target. The
+//! target is then revealed, and shapeshifts: its embedding is pushed by
+//! random noise of norm [`DRIFT_RADIUS`] and projected back to the nearest
+//! other vocabulary word. The target never jumps far, so players who follow
+//! the revealed targets can track where it is heading.
+//!
+//! Submitting a word you already submitted in an earlier round costs
+//! [`REPEAT_PENALTY`] instead of scoring, so sitting on one good guess does
+//! not pay.
+
+use once_cell::sync::Lazy;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+use crate::embeddings::{cosine_similarity, normalize, EmbeddingProvider, NgramEmbeddings};
+
+/// Points for a submission identical to the target; others score in
+/// proportion to their similarity
+pub const SIMILARITY_POINTS: i32 = 100;
+
+/// Points lost for submitting a word again
+pub const REPEAT_PENALTY: i32 = 25;
+
+/// Norm of the noise added to the target's unit embedding each round
+pub const DRIFT_RADIUS: f32 = 0.35;
+
+/// Longest accepted submission, in characters
+pub const MAX_SUBMISSION_CHARS: usize = 48;
+
+/// Mixed into the game seed for the target's own RNG stream
+const DRIFT_STREAM: u64 = 0x5348_4150_4553_4846;
+
+static BUILTIN: Lazy<Arc<Vocabulary>> = Lazy::new(|| {
+    let words = parse_vocabulary(include_str!("../assets/shapeshifter_vocabulary.txt"));
+    Arc::new(Vocabulary::new(Box::new(NgramEmbeddings::default()), words))
+});
+
+/// Words from a vocabulary file: one per line, skipping blank lines,
+/// `#` comments and duplicates
+pub fn parse_vocabulary(text: &str) -> Vec<String> {
+    let mut words: Vec<String> = Vec::new();
+    for line in text.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let word = normalize_submission(line);
+        if !words.contains(&word) {
+            words.push(word);
+        }
+    }
+    words
+}
+
+/// The words a target can be, with their embeddings scaled to unit length
+pub struct Vocabulary {
+    provider: Box<dyn EmbeddingProvider>,
+    words: Vec<String>,
+    embeddings: Vec<Vec<f32>>,
+    /// Non-zero components of each embedding; hashed n-gram embeddings
+    /// have only a handful, which keeps drifting cheap in bot simulations
+    sparse: Vec<Vec<(usize, f32)>>,
+    index: HashMap<String, usize>,
+}
+
+impl Vocabulary {
+    pub fn new(provider: Box<dyn EmbeddingProvider>, words: Vec<String>) -> Self {
+        let embeddings: Vec<Vec<f32>> = words.iter().map(|word| unit(provider.embed(word))).collect();
+        let sparse = embeddings.iter()
+            .map(|e| e.iter().copied().enumerate().filter(|(_, v)| *v != 0.0).collect())
+            .collect();
+        let index = words.iter().enumerate().map(|(i, word)| (word.clone(), i)).collect();
+        Self { provider, words, embeddings, sparse, index }
+    }
+
+    /// The vocabulary shipped in `assets/shapeshifter_vocabulary.txt`
+    pub fn builtin() -> Arc<Self> {
+        BUILTIN.clone()
+    }
+
+    pub fn provider(&self) -> String {
+        self.provider.provider()
+    }
+
+    pub fn words(&self) -> &[String] {
+        &self.words
+    }
+
+    /// Similarity of `text` to the word at `index`
+    pub fn similarity(&self, text: &str, index: usize) -> f32 {
+        let target = &self.embeddings[index];
+        match self.index.get(text) {
+            Some(&i) => dot(&self.embeddings[i], target),
+            None => cosine_similarity(&self.provider.embed(text), target),
+        }
+    }
+
+    /// The word closest to `point`, other than the one at `exclude`
+    fn nearest(&self, point: &[f32], exclude: usize) -> usize {
+        // Unnormalized: scaling `point` does not change which word is nearest
+        let mut best = (exclude, f32::MIN);
+        for (i, embedding) in self.sparse.iter().enumerate() {
+            let similarity: f32 = embedding.iter().map(|(j, v)| point[*j] * v).sum();
+            if i != exclude && similarity > best.1 {
+                best = (i, similarity);
+            }
+        }
+        best.0
+    }
+}
+
+impl fmt::Debug for Vocabulary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Vocabulary")
+            .field("provider", &self.provider())
+            .field("words", &self.words.len())
+            .finish()
+    }
+}
+
+/// The hidden target and the RNG it drifts with; server-side only
+///
+/// The target draws from its own stream derived from the game seed rather
+/// than from the game's RNG, so bot seeds drawn when a game is created do
+/// not change where it goes.
+#[derive(Clone)]
+pub struct Shapeshifter {
+    vocabulary: Arc<Vocabulary>,
+    rng: StdRng,
+    target: Option<usize>,
+    revealed: Option<usize>,
+}
+
+impl Shapeshifter {
+    pub fn new(vocabulary: Arc<Vocabulary>, seed: u64) -> Self {
+        Self { vocabulary, rng: StdRng::seed_from_u64(seed ^ DRIFT_STREAM), target: None, revealed: None }
+    }
+
+    /// A copy for simulations that takes the last revealed target for the
+    /// hidden one, so bots cannot peek at it
+    pub fn simulation(&self) -> Self {
+        Self { target: self.revealed, ..self.clone() }
+    }
+
+    pub fn vocabulary(&self) -> &Vocabulary {
+        &self.vocabulary
+    }
+
+    /// The hidden target, once the game has started
+    pub fn target(&self) -> Option<&str> {
+        self.target.map(|i| self.vocabulary.words[i].as_str())
+    }
+
+    /// Choose the first target
+    pub fn pick_target(&mut self) {
+        if !self.vocabulary.words.is_empty() {
+            self.target = Some(self.rng.gen_range(0..self.vocabulary.words.len()));
+        }
+    }
+
+    /// Similarity of `text` to the hidden target; 0 before there is one
+    pub fn similarity(&self, text: &str) -> f32 {
+        self.target.map_or(0.0, |target| self.vocabulary.similarity(text, target))
+    }
+
+    /// What players can know of [`similarity`](Self::similarity): the
+    /// similarity to the last revealed target
+    pub fn estimate(&self, text: &str) -> f32 {
+        self.revealed.map_or(0.0, |revealed| self.vocabulary.similarity(text, revealed))
+    }
+
+    /// Reveal the target and shapeshift it for the next round
+    ///
+    /// Returns the revealed word and its similarity to the target revealed
+    /// before it.
+    pub fn reveal(&mut self) -> Option<(String, Option<f32>)> {
+        let target = self.target?;
+        let drift = self.revealed.map(|previous| {
+            dot(&self.vocabulary.embeddings[previous], &self.vocabulary.embeddings[target])
+        });
+        self.revealed = Some(target);
+        self.target = Some(self.drift(target));
+        Some((self.vocabulary.words[target].clone(), drift))
+    }
+
+    /// Nearest other word to the target's embedding plus bounded noise
+    fn drift(&mut self, from: usize) -> usize {
+        let embedding = &self.vocabulary.embeddings[from];
+        if self.vocabulary.words.len() < 2 {
+            return from;
+        }
+        let mut noise: Vec<f32> = (0..embedding.len()).map(|_| self.rng.gen_range(-1.0..1.0)).collect();
+        normalize(&mut noise);
+        let point: Vec<f32> = embedding.iter().zip(&noise).map(|(e, n)| e + DRIFT_RADIUS * n).collect();
+        self.vocabulary.nearest(&point, from)
+    }
+}
+
+fn unit(mut v: Vec<f32>) -> Vec<f32> {
+    normalize(&mut v);
+    v
+}
+
+/// Cosine similarity of two unit vectors
+fn dot(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+impl fmt::Debug for Shapeshifter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Shapeshifter")
+            .field("vocabulary", &self.vocabulary)
+            .field("target", &self.target())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Shapeshifter bookkeeping everyone may see
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ShapeshifterState {
+    /// Words submitted so far this round, by player
+    pub submissions: HashMap<String, String>,
+    /// Every finished round, with its target revealed
+    pub rounds: Vec<ShapeshifterRound>,
+}
+
+impl ShapeshifterState {
+    /// Whether `player_id` submitted `word` in an earlier round
+    pub fn submitted_before(&self, player_id: &str, word: &str) -> bool {
+        self.rounds.iter()
+            .flat_map(|round| &round.scores)
+            .any(|score| score.player_id == player_id && score.word == word)
+    }
+}
+
+/// A finished round
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ShapeshifterRound {
+    pub round: u32,
+    pub target: String,
+    /// Similarity of the target to the previous round's; `None` in the first round
+    pub drift: Option<f32>,
+    /// Scored submissions, by player id
+    pub scores: Vec<SubmissionScore>,
+}
+
+/// How one submission scored
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SubmissionScore {
+    pub player_id: String,
+    pub word: String,
+    pub similarity: f32,
+    pub points: i32,
+    /// Submitted by the same player in an earlier round
+    pub repeat: bool,
+}
+
+/// Lowercase with single spaces, the form submissions are compared in
+pub fn normalize_submission(word: &str) -> String {
+    word.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase()
+}
+
+/// Check a normalized submission
+pub fn validate_submission(word: &str) -> Result<(), String> {
+    if word.is_empty() {
+        return Err("Submit a word or phrase".to_string());
+    }
+    if word.chars().count() > MAX_SUBMISSION_CHARS {
+        return Err(format!("Submissions are limited to {} characters", MAX_SUBMISSION_CHARS));
+    }
+    if !word.chars().any(char::is_alphabetic) {
+        return Err("Submissions must contain letters".to_string());
+    }
+    Ok(())
+}
+
+/// Points for a submission with the given similarity to the target
+pub fn points(similarity: f32, repeat: bool) -> i32 {
+    if repeat {
+        -REPEAT_PENALTY
+    } else {
+        (similarity.max(0.0) * SIMILARITY_POINTS as f32).round() as i32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analytics::GameAnalytics;
+    use crate::{new_game, rules, GameAction, GameState, GameStatus, GameType, Player, PlayerType};
+
+    /// Eight colours evenly spaced on the unit circle, 45 degrees apart;
+    /// anything else embeds to zero
+    const COLOURS: [&str; 8] = ["red", "orange", "yellow", "green", "cyan", "blue", "violet", "magenta"];
+
+    struct Fixture;
+
+    impl EmbeddingProvider for Fixture {
+        fn provider(&self) -> String {
+            "fixture".to_string()
+        }
+
+        fn embed(&self, text: &str) -> Vec<f32> {
+            match COLOURS.iter().position(|c| *c == text) {
+                Some(i) => {
+                    let angle = i as f32 * std::f32::consts::FRAC_PI_4;
+                    vec![angle.cos(), angle.sin()]
+                }
+                None => vec![0.0, 0.0],
+            }
+        }
+    }
+
+    fn game(seed: u64, max_rounds: u32) -> GameState {
+        let mut state = new_game(GameType::SemanticShapeshifter, max_rounds, Some(seed));
+        let words = COLOURS.iter().map(|c| c.to_string()).collect();
+        state.rules.set_vocabulary(Arc::new(Vocabulary::new(Box::new(Fixture), words)));
+        for id in ["alice", "bob"] {
+            rules::add_player(&mut state, Player {
+                id: id.to_string(),
+                name: id.to_string(),
+                player_type: PlayerType::SingleAI { model: "human".to_string() },
+                score: 0,
+                neurons_placed: 0,
+                color: "#00ffff".to_string(),
+            });
+        }
+        rules::start_game(&mut state).unwrap();
+        state
+    }
+
+    fn submit(state: &mut GameState, player_id: &str, word: &str) -> Result<(), String> {
+        rules::apply_action(state, player_id, GameAction::SubmitWord { word: word.to_string() })
+    }
+
+    /// Index of the hidden target among `COLOURS`
+    fn target(state: &GameState) -> usize {
+        let target = state.rules.shapeshifter().target().unwrap();
+        COLOURS.iter().position(|c| *c == target).unwrap()
+    }
+
+    #[test]
+    fn test_submissions_score_by_similarity_to_hidden_target() {
+        let mut state = game(3, 10);
+        let t = target(&state);
+        // The target stays hidden until the round ends
+        let json = serde_json::to_string(&state).unwrap();
+        assert!(!json.contains(&format!("\"{}\"", COLOURS[t])), "{}", json);
+
+        submit(&mut state, "alice", COLOURS[t]).unwrap();
+        submit(&mut state, "bob", &format!("  {} ", COLOURS[(t + 1) % 8].to_uppercase())).unwrap();
+        assert!(submit(&mut state, "bob", COLOURS[t]).is_err(), "one submission per round");
+        rules::end_round(&mut state);
+
+        assert_eq!(state.players["alice"].score, 100);
+        // cos 45 degrees
+        assert_eq!(state.players["bob"].score, 71);
+        let round = &state.shapeshifter.rounds[0];
+        assert_eq!((round.round, round.target.as_str(), round.drift), (0, COLOURS[t], None));
+        assert_eq!(round.scores[1].word, COLOURS[(t + 1) % 8]);
+        assert!(state.shapeshifter.submissions.is_empty());
+
+        // Opposite the target scores nothing, and unknown words are orthogonal
+        let t = target(&state);
+        submit(&mut state, "alice", COLOURS[(t + 4) % 8]).unwrap();
+        submit(&mut state, "bob", "sandwich").unwrap();
+        rules::end_round(&mut state);
+        assert_eq!((state.players["alice"].score, state.players["bob"].score), (100, 71));
+
+        let analytics = GameAnalytics::of(&state);
+        let shapeshifter = analytics.shapeshifter.unwrap();
+        assert_eq!(shapeshifter.drift.len(), 2);
+        assert_eq!(shapeshifter.drift[1].target, COLOURS[t]);
+        let bob: Vec<i32> = shapeshifter.similarity_history["bob"].iter().map(|s| s.points).collect();
+        assert_eq!(bob, vec![71, 0]);
+    }
+
+    #[test]
+    fn test_repeat_submissions_are_penalized() {
+        let mut state = game(5, 10);
+        submit(&mut state, "alice", "red").unwrap();
+        rules::end_round(&mut state);
+        let before = state.players["alice"].score;
+
+        submit(&mut state, "alice", " Red").unwrap();
+        submit(&mut state, "bob", "red").unwrap();
+        rules::end_round(&mut state);
+
+        assert_eq!(state.players["alice"].score, before - REPEAT_PENALTY);
+        let scores = &state.shapeshifter.rounds[1].scores;
+        assert!(scores[0].repeat);
+        // Someone else's earlier word is fair game
+        assert!(!scores[1].repeat);
+        assert!(scores[1].points >= 0);
+    }
+
+    #[test]
+    fn test_target_drifts_to_neighbouring_concepts() {
+        let trajectory = |seed: u64| {
+            let mut state = game(seed, 30);
+            while state.status == GameStatus::Running {
+                rules::end_round(&mut state);
+            }
+            state.shapeshifter.rounds.iter().map(|r| r.target.clone()).collect::<Vec<_>>()
+        };
+
+        let targets = trajectory(11);
+        assert_eq!(targets.len(), 30);
+        for pair in targets.windows(2) {
+            let a = COLOURS.iter().position(|c| *c == pair[0]).unwrap();
+            let b = COLOURS.iter().position(|c| *c == pair[1]).unwrap();
+            // Noise of norm 0.35 moves the target less than 22.5 degrees,
+            // so the nearest other colour is always a neighbour
+            assert!(matches!((a + 8 - b) % 8, 1 | 7), "{} -> {}", pair[0], pair[1]);
+        }
+        assert_eq!(trajectory(11), targets);
+    }
+}
@@ -2,7 +2,7 @@
 //! In production, this should be replaced with a proper database (PostgreSQL)
 
 use crate::auth::{User, Role, hash_password};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use uuid::Uuid;
@@ -12,6 +12,7 @@ use chrono::Utc;
 pub struct UserStore {
     users: Arc<RwLock<HashMap<String, User>>>,
     username_index: Arc<RwLock<HashMap<String, String>>>, // username -> user_id
+    game_bans: Arc<RwLock<HashMap<String, HashSet<String>>>>, // user_id -> banned game ids
 }
 
 impl UserStore {
@@ -19,6 +20,7 @@ impl UserStore {
         Self {
             users: Arc::new(RwLock::new(HashMap::new())),
             username_index: Arc::new(RwLock::new(HashMap::new())),
+            game_bans: Arc::new(RwLock::new(HashMap::new())),
         }
     }
     
@@ -113,6 +115,25 @@ impl UserStore {
             None => Err("User not found".to_string()),
         }
     }
+    
+    /// Ban a user from a game (admin only)
+    pub async fn ban_from_game(&self, user_id: &str, game_id: &str) -> Result<(), String> {
+        if !self.users.read().await.contains_key(user_id) {
+            return Err("User not found".to_string());
+        }
+        self.game_bans.write().await
+            .entry(user_id.to_string())
+            .or_default()
+            .insert(game_id.to_string());
+        Ok(())
+    }
+    
+    /// Whether a user is banned from a game
+    pub async fn is_banned_from_game(&self, user_id: &str, game_id: &str) -> bool {
+        self.game_bans.read().await
+            .get(user_id)
+            .is_some_and(|games| games.contains(game_id))
+    }
 }
 
 impl Default for UserStore {
@@ -154,7 +154,7 @@ main() {
     log_info "🧠 Checking neurons..."
     local neurons=$(curl -s "http://localhost:$HAL9_PORT_MAIN/api/v1/neurons" 2>/dev/null)
     if [ -n "$neurons" ]; then
-        local neuron_count=$(echo "$neurons" | jq '. | length' 2>/dev/null || echo "0")
+        local neuron_count=$(echo "$neurons" | jq '.data.total_estimate' 2>/dev/null || echo "0")
         log_info "Found $neuron_count neurons"
         echo "$neurons" | jq '.' 2>/dev/null || echo "$neurons"
     else
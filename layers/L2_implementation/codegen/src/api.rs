@@ -1,42 +1,31 @@
 //! API client for HAL9 code generation service
 
 use anyhow::{Context, Result};
-use reqwest::{Client, header};
+use futures_util::StreamExt;
+use hal9_client::{ClientConfig, Credentials, Hal9Client};
 use serde::{Deserialize, Serialize};
 use std::time::Duration;
+use tokio::net::TcpStream;
+use tokio_tungstenite::tungstenite::{client::IntoClientRequest, Message};
+use tokio_tungstenite::{connect_async, MaybeTlsStream, WebSocketStream};
+
+/// Times a dropped job stream is reopened before giving up
+const MAX_STREAM_RECONNECTS: u32 = 5;
 
 /// Code generation API client
 pub struct CodegenClient {
-    client: Client,
-    base_url: String,
-    api_key: Option<String>,
+    client: Hal9Client,
 }
 
 impl CodegenClient {
     /// Create a new API client
     pub fn new(base_url: &str, api_key: Option<String>) -> Result<Self> {
-        let mut headers = header::HeaderMap::new();
-        headers.insert(
-            header::CONTENT_TYPE,
-            header::HeaderValue::from_static("application/json"),
-        );
-        
-        if let Some(ref key) = api_key {
-            headers.insert(
-                header::HeaderName::from_static("x-api-key"),
-                header::HeaderValue::from_str(key)?,
-            );
-        }
-        
-        let client = Client::builder()
-            .default_headers(headers)
-            .timeout(Duration::from_secs(300)) // 5 minutes for large generations
-            .build()?;
+        let config = ClientConfig::new(base_url)
+            .credentials(api_key.map(Credentials::ApiKey).unwrap_or_default())
+            .timeout(Duration::from_secs(300)); // 5 minutes for large generations
         
         Ok(Self {
-            client,
-            base_url: base_url.trim_end_matches('/').to_string(),
-            api_key,
+            client: Hal9Client::new(config)?,
         })
     }
     
@@ -72,34 +61,72 @@ impl CodegenClient {
             },
         };
         
-        let response = self.client
-            .post(format!("{}/api/v1/codegen/project", self.base_url))
-            .json(&request)
-            .send()
+        self.client
+            .post("/api/v1/codegen/project", &request)
             .await
-            .context("Failed to send project generation request")?;
-        
-        if !response.status().is_success() {
-            let error_text = response.text().await?;
-            return Err(anyhow::anyhow!("API error: {}", error_text));
-        }
-        
-        response.json::<GenerateProjectResponse>()
-            .await
-            .context("Failed to parse project generation response")
+            .context("Failed to generate project")
     }
     
     /// Get project generation status
     pub async fn get_project_status(&self, project_id: &str) -> Result<ProjectStatus> {
-        let response = self.client
-            .get(format!("{}/api/v1/codegen/project/{}", self.base_url, project_id))
-            .send()
+        self.client
+            .get(&format!("/api/v1/codegen/project/{}", project_id), &[])
             .await
-            .context("Failed to get project status")?;
+            .context("Failed to get project status")
+    }
+    
+    /// Follow a generation job's progress events until its terminal event,
+    /// reopening the stream from the next unseen event when it drops. Fails
+    /// at once if the server does not accept the WebSocket.
+    pub async fn follow_job(&self, project_id: &str, mut on_event: impl FnMut(&JobEvent)) -> Result<JobOutcome> {
+        let mut next_seq = 1;
+        let mut reconnects = 0;
+        let mut socket = self.open_job_stream(project_id, next_seq).await?;
         
-        response.json::<ProjectStatus>()
-            .await
-            .context("Failed to parse project status")
+        loop {
+            while let Some(message) = socket.next().await {
+                let text = match message {
+                    Ok(Message::Text(text)) => text,
+                    Ok(Message::Close(_)) | Err(_) => break,
+                    Ok(_) => continue,
+                };
+                let event: JobEvent = serde_json::from_str(&text).context("Malformed job event")?;
+                next_seq = event.seq + 1;
+                on_event(&event);
+                match event.kind {
+                    JobEventKind::Completed { manifest } => return Ok(JobOutcome::Completed(Some(manifest))),
+                    JobEventKind::Failed { error } => return Ok(JobOutcome::Failed(error)),
+                    _ => {}
+                }
+            }
+            
+            reconnects += 1;
+            if reconnects > MAX_STREAM_RECONNECTS {
+                return Err(anyhow::anyhow!("Job stream dropped {} times", reconnects));
+            }
+            tokio::time::sleep(Duration::from_millis(500)).await;
+            socket = self.open_job_stream(project_id, next_seq).await?;
+        }
+    }
+    
+    async fn open_job_stream(
+        &self,
+        project_id: &str,
+        from_seq: u64,
+    ) -> Result<WebSocketStream<MaybeTlsStream<TcpStream>>> {
+        let config = self.client.config();
+        let base_url = config.base_url
+            .replacen("https://", "wss://", 1)
+            .replacen("http://", "ws://", 1);
+        let url = format!("{}/api/v1/codegen/jobs/{}/stream?from_seq={}", base_url, project_id, from_seq);
+        
+        let mut request = url.into_client_request()?;
+        if let Credentials::ApiKey(key) = &config.credentials {
+            request.headers_mut().insert("X-API-Key", key.parse()?);
+        }
+        
+        let (socket, _) = connect_async(request).await.context("Failed to open job stream")?;
+        Ok(socket)
     }
     
     /// Get code completion suggestions
@@ -117,16 +144,10 @@ impl CodegenClient {
             language,
         };
         
-        let response = self.client
-            .post(format!("{}/api/v1/codegen/complete", self.base_url))
-            .json(&request)
-            .send()
+        self.client
+            .post("/api/v1/codegen/complete", &request)
             .await
-            .context("Failed to get code completion")?;
-        
-        response.json::<CodeCompletionResponse>()
-            .await
-            .context("Failed to parse completion response")
+            .context("Failed to get code completion")
     }
     
     /// Review code
@@ -151,16 +172,10 @@ impl CodegenClient {
             focus: focus_areas,
         };
         
-        let response = self.client
-            .post(format!("{}/api/v1/codegen/review", self.base_url))
-            .json(&request)
-            .send()
+        self.client
+            .post("/api/v1/codegen/review", &request)
             .await
-            .context("Failed to review code")?;
-        
-        response.json::<CodeReviewResponse>()
-            .await
-            .context("Failed to parse review response")
+            .context("Failed to review code")
     }
     
     /// Refactor code
@@ -198,29 +213,18 @@ impl CodegenClient {
             selection,
         };
         
-        let response = self.client
-            .post(format!("{}/api/v1/codegen/refactor", self.base_url))
-            .json(&request)
-            .send()
-            .await
-            .context("Failed to refactor code")?;
-        
-        response.json::<RefactorResponse>()
+        self.client
+            .post("/api/v1/codegen/refactor", &request)
             .await
-            .context("Failed to parse refactor response")
+            .context("Failed to refactor code")
     }
     
     /// Get available templates
     pub async fn get_templates(&self) -> Result<TemplatesResponse> {
-        let response = self.client
-            .get(format!("{}/api/v1/codegen/templates", self.base_url))
-            .send()
-            .await
-            .context("Failed to get templates")?;
-        
-        response.json::<TemplatesResponse>()
+        self.client
+            .get("/api/v1/codegen/templates", &[])
             .await
-            .context("Failed to parse templates")
+            .context("Failed to get templates")
     }
 }
 
@@ -268,6 +272,49 @@ pub struct ProjectStatus {
     pub progress: u32,
     pub files_generated: u32,
     pub location: String,
+    #[serde(default)]
+    pub manifest: Option<JobManifest>,
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+/// A progress event of a generation job
+#[derive(Debug, Deserialize)]
+pub struct JobEvent {
+    pub seq: u64,
+    #[serde(flatten)]
+    pub kind: JobEventKind,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum JobEventKind {
+    Stage { stage: String, progress: u32 },
+    FileCompleted { path: String, bytes: u64 },
+    Warning { message: String },
+    Completed { manifest: JobManifest },
+    Failed { error: String },
+}
+
+#[derive(Debug, Deserialize)]
+pub struct JobManifest {
+    pub location: String,
+    pub files: Vec<ManifestFile>,
+    pub total_bytes: u64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ManifestFile {
+    pub path: String,
+    pub bytes: u64,
+}
+
+/// How a generation job ended
+#[derive(Debug)]
+pub enum JobOutcome {
+    /// Finished; polling servers may not report a manifest
+    Completed(Option<JobManifest>),
+    Failed(String),
 }
 
 #[derive(Debug, Serialize)]
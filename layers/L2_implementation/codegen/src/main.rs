@@ -12,22 +12,30 @@ use std::time::Duration;
 mod api;
 mod config;
 
-use api::CodegenClient;
+use api::{CodegenClient, JobEvent, JobEventKind, JobOutcome};
 use config::Config;
+use hal9_client::profile::ProfileFile;
+
+/// Server used when neither flags, a profile nor the config name one
+const DEFAULT_SERVER: &str = "http://localhost:8080";
 
 /// HAL9 Code Generation Assistant
 #[derive(Parser)]
 #[command(name = "hal9-codegen")]
 #[command(about = "AI-powered code generation using HAL9", long_about = None)]
 struct Cli {
-    /// HAL9 server URL
-    #[arg(short, long, env = "HAL9_SERVER_URL", default_value = "http://localhost:8080")]
-    server: String,
+    /// HAL9 server URL [default: the profile's, else the configured one, else http://localhost:8080]
+    #[arg(short, long, env = "HAL9_SERVER_URL")]
+    server: Option<String>,
     
     /// API key for authentication
     #[arg(short = 'k', long, env = "HAL9_API_KEY")]
     api_key: Option<String>,
     
+    /// Profile from ~/.config/hal9/config.toml, shared with the hal9 CLI [default: $HAL9_PROFILE, else the file's default]
+    #[arg(long)]
+    profile: Option<String>,
+    
     /// Enable verbose output
     #[arg(short, long)]
     verbose: bool,
@@ -147,13 +155,20 @@ async fn main() -> Result<()> {
             .init();
     }
     
-    // Load configuration
+    // Load configuration; flags win over the profile, which wins over it
     let config = Config::load()?;
+    let profile = ProfileFile::load_default()?.resolve_from_env(cli.profile.as_deref())?;
+    let server = cli.server
+        .or_else(|| profile.as_ref().map(|p| p.server.clone()))
+        .or(config.server_url)
+        .unwrap_or_else(|| DEFAULT_SERVER.to_string());
+    // A profile's key is only sent to the profile's own server
+    let profile_key = profile.filter(|p| p.server == server).and_then(|p| p.api_key);
     
     // Create API client
     let client = CodegenClient::new(
-        &cli.server,
-        cli.api_key.or(config.api_key),
+        &server,
+        cli.api_key.or(profile_key).or(config.api_key),
     )?;
     
     // Handle commands
@@ -355,24 +370,29 @@ async fn generate_new_project(
         features.3, // ci/cd
     ).await?;
     
-    // Simulate progress (in production, poll for actual progress)
-    for i in 0..100 {
-        pb.set_position(i);
-        pb.set_message(match i {
-            0..=20 => "Creating project structure...",
-            21..=40 => "Generating backend code...",
-            41..=60 => "Generating frontend code...",
-            61..=80 => "Setting up configuration...",
-            81..=95 => "Adding tests and documentation...",
-            _ => "Finalizing...",
-        });
-        tokio::time::sleep(Duration::from_millis(50)).await;
-    }
+    // Follow the job live, polling its status if the server cannot stream it
+    let outcome = match client.follow_job(&response.project_id, |event| show_job_event(&pb, event)).await {
+        Ok(outcome) => outcome,
+        Err(_) => poll_job(&client, &response.project_id, &pb).await?,
+    };
     
+    let manifest = match outcome {
+        JobOutcome::Completed(manifest) => manifest,
+        JobOutcome::Failed(error) => {
+            pb.abandon_with_message("Failed");
+            return Err(anyhow::anyhow!("Project generation failed: {}", error));
+        }
+    };
     pb.finish_with_message("Complete!");
     
     println!("\n{} Project generated successfully!", "✅".bright_green());
-    println!("📁 Location: {}", response.location.unwrap_or_else(|| "./generated".to_string()).bright_white());
+    let location = manifest.as_ref().map(|m| m.location.clone())
+        .or(response.location)
+        .unwrap_or_else(|| "./generated".to_string());
+    println!("📁 Location: {}", location.bright_white());
+    if let Some(manifest) = manifest {
+        println!("📄 {} files, {} bytes", manifest.files.len(), manifest.total_bytes);
+    }
     println!("\n{}", "Next steps:".bright_blue().bold());
     println!("  1. cd {}", project_name);
     println!("  2. Follow the README.md for setup instructions");
@@ -381,6 +401,38 @@ async fn generate_new_project(
     Ok(())
 }
 
+/// Reflect a streamed job event on the progress bar
+fn show_job_event(pb: &ProgressBar, event: &JobEvent) {
+    match &event.kind {
+        JobEventKind::Stage { stage, progress } => {
+            pb.set_position(*progress as u64);
+            pb.set_message(format!("{}...", stage));
+        }
+        JobEventKind::FileCompleted { path, bytes } => {
+            pb.set_message(format!("{} ({} bytes)", path, bytes));
+        }
+        JobEventKind::Warning { message } => {
+            pb.println(format!("{} {}", "⚠️".yellow(), message));
+        }
+        JobEventKind::Completed { .. } | JobEventKind::Failed { .. } => {}
+    }
+}
+
+/// Poll the job status until it finishes, for servers without the stream
+async fn poll_job(client: &CodegenClient, project_id: &str, pb: &ProgressBar) -> Result<JobOutcome> {
+    loop {
+        let status = client.get_project_status(project_id).await?;
+        pb.set_position(status.progress as u64);
+        pb.set_message(format!("{} files generated", status.files_generated));
+        
+        match status.status.as_str() {
+            "completed" => return Ok(JobOutcome::Completed(status.manifest)),
+            "failed" => return Ok(JobOutcome::Failed(status.error.unwrap_or_default())),
+            _ => tokio::time::sleep(Duration::from_millis(500)).await,
+        }
+    }
+}
+
 async fn add_feature(
     client: CodegenClient,
     feature: String,
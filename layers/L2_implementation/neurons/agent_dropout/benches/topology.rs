@@ -0,0 +1,72 @@
+//! Network topology benchmarks on networks of up to 10,000 agents: placing
+//! an agent, connecting two and reading the network stats
+//!
+//! Each placed agent is removed again outside the measured time, so the
+//! network keeps its size. Connections made are kept, which is why connecting
+//! is measured last.
+
+use agent_dropout::{AgentLevel, AgentProfile, NetworkTopology};
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use std::time::{Duration, Instant};
+use tokio::runtime::Runtime;
+use uuid::Uuid;
+
+const NETWORK_SIZES: [usize; 3] = [100, 1_000, 10_000];
+
+fn profile(rng: &mut StdRng) -> AgentProfile {
+    let level = AgentLevel::from_value(rng.gen_range(1..=20)).unwrap();
+    AgentProfile::new(Uuid::from_u128(rng.gen()), level)
+}
+
+/// A network of `size` agents placed the way new agents join
+fn network(rt: &Runtime, rng: &mut StdRng, size: usize) -> (NetworkTopology, Vec<Uuid>) {
+    let topology = NetworkTopology::new();
+    let mut ids = Vec::with_capacity(size);
+    for _ in 0..size {
+        let agent = profile(rng);
+        rt.block_on(topology.place_agent(&agent));
+        ids.push(agent.id);
+    }
+    (topology, ids)
+}
+
+fn bench_topology(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let mut rng = StdRng::seed_from_u64(7);
+    let mut group = c.benchmark_group("topology");
+
+    for size in NETWORK_SIZES {
+        let (topology, ids) = network(&rt, &mut rng, size);
+
+        group.bench_with_input(BenchmarkId::new("network_stats", size), &topology, |b, topology| {
+            b.iter(|| rt.block_on(topology.get_network_stats()))
+        });
+        group.bench_with_input(BenchmarkId::new("place_agent", size), &topology, |b, topology| {
+            b.iter_custom(|iters| {
+                let mut elapsed = Duration::ZERO;
+                for _ in 0..iters {
+                    let agent = profile(&mut rng);
+                    let start = Instant::now();
+                    rt.block_on(topology.place_agent(black_box(&agent)));
+                    elapsed += start.elapsed();
+                    rt.block_on(topology.remove_agent(agent.id));
+                }
+                elapsed
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("connect_agents", size), &topology, |b, topology| {
+            b.iter(|| {
+                let pair = ids.choose_multiple(&mut rng, 2).copied().collect::<Vec<_>>();
+                rt.block_on(topology.connect_agents(pair[0], pair[1], 0.5))
+            })
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_topology);
+criterion_main!(benches);
@@ -0,0 +1,308 @@
+//! Adjacency storage of the agent network
+//!
+//! Agents and directed connections are kept in slot vectors that are removed
+//! from by moving the last slot into the freed one, and a removed agent's
+//! connections go in the order petgraph's `Graph` removed them in, so both
+//! iterate as they did when the network was a petgraph. Every agent keeps
+//! the slots of its connections per peer, its distinct neighbors either way
+//! and the number of links among those neighbors, updated as connections
+//! come and go: finding a connection or a degree is O(1), walking an agent's
+//! connections O(deg), and network statistics never revisit pairs of
+//! neighbors.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use uuid::Uuid;
+
+use crate::network::{AgentNode, ConnectionEdge};
+
+/// A directed connection between two agents
+#[derive(Clone, Debug)]
+pub(crate) struct Edge {
+    pub from: Uuid,
+    pub to: Uuid,
+    pub weight: ConnectionEdge,
+    /// Order the connection was added in
+    seq: u64,
+}
+
+struct Slot {
+    node: AgentNode,
+    /// Slots of the connections to each peer, oldest first; parallel
+    /// connections are kept
+    outgoing: HashMap<Uuid, Vec<usize>>,
+    /// Slots of the connections from each peer, oldest first
+    incoming: HashMap<Uuid, Vec<usize>>,
+    /// Connections to peers, parallel ones included
+    out_degree: usize,
+    /// Agents connected either way, not counting the agent itself
+    neighbors: HashSet<Uuid>,
+    /// Links between two of `neighbors`
+    triangles: usize,
+}
+
+impl Slot {
+    /// Local clustering coefficient, `None` below two neighbors
+    fn clustering(&self) -> Option<f64> {
+        let degree = self.neighbors.len();
+        (degree >= 2).then(|| (2 * self.triangles) as f64 / (degree * (degree - 1)) as f64)
+    }
+}
+
+/// Agents and their directed connections
+#[derive(Default)]
+pub(crate) struct AgentGraph {
+    slots: Vec<Slot>,
+    index: HashMap<Uuid, usize>,
+    edges: Vec<Edge>,
+    next_seq: u64,
+    /// Number of agents by count of neighbors
+    degree_distribution: BTreeMap<usize, usize>,
+}
+
+impl AgentGraph {
+    pub fn node_count(&self) -> usize {
+        self.slots.len()
+    }
+
+    pub fn edge_count(&self) -> usize {
+        self.edges.len()
+    }
+
+    pub fn contains(&self, id: &Uuid) -> bool {
+        self.index.contains_key(id)
+    }
+
+    pub fn node(&self, id: &Uuid) -> Option<&AgentNode> {
+        self.index.get(id).map(|&slot| &self.slots[slot].node)
+    }
+
+    pub fn node_mut(&mut self, id: &Uuid) -> Option<&mut AgentNode> {
+        let slot = *self.index.get(id)?;
+        Some(&mut self.slots[slot].node)
+    }
+
+    /// Agents in slot order
+    pub fn nodes(&self) -> impl Iterator<Item = &AgentNode> {
+        self.slots.iter().map(|slot| &slot.node)
+    }
+
+    /// Connections in slot order
+    pub fn edges(&self) -> impl Iterator<Item = &Edge> {
+        self.edges.iter()
+    }
+
+    /// Add an agent. An agent already in the graph takes the new node and
+    /// keeps its connections. Returns whether the agent is new.
+    pub fn add_node(&mut self, node: AgentNode) -> bool {
+        if let Some(&slot) = self.index.get(&node.id) {
+            self.slots[slot].node = node;
+            return false;
+        }
+
+        self.index.insert(node.id, self.slots.len());
+        self.slots.push(Slot {
+            node,
+            outgoing: HashMap::new(),
+            incoming: HashMap::new(),
+            out_degree: 0,
+            neighbors: HashSet::new(),
+            triangles: 0,
+        });
+        *self.degree_distribution.entry(0).or_insert(0) += 1;
+        true
+    }
+
+    /// Remove an agent and its connections, O(deg)
+    pub fn remove_node(&mut self, id: &Uuid) -> Option<AgentNode> {
+        let slot = *self.index.get(id)?;
+        // Outgoing connections latest first, then incoming ones; self loops
+        // go with the outgoing
+        let outgoing = self.latest_first(&self.slots[slot].outgoing);
+        let mut incoming = self.latest_first(&self.slots[slot].incoming);
+        incoming.retain(|(_, from, _)| from != id);
+        for (seq, from, to) in outgoing.into_iter().chain(incoming) {
+            let source = self.index[&from];
+            let edge = self.slots[source].outgoing[&to].iter()
+                .copied()
+                .find(|&edge| self.edges[edge].seq == seq);
+            if let Some(edge) = edge {
+                self.remove_edge(edge);
+            }
+        }
+
+        self.count_degree(0, -1);
+        self.index.remove(id);
+        let removed = self.slots.swap_remove(slot);
+        if let Some(moved) = self.slots.get(slot) {
+            self.index.insert(moved.node.id, slot);
+        }
+        Some(removed.node)
+    }
+
+    /// Connect `from` to `to`, alongside any connection already there.
+    /// Returns false, adding nothing, unless both agents are in the graph.
+    pub fn add_edge(&mut self, from: Uuid, to: Uuid, weight: ConnectionEdge) -> bool {
+        let (a, b) = match (self.index.get(&from), self.index.get(&to)) {
+            (Some(&a), Some(&b)) => (a, b),
+            _ => return false,
+        };
+
+        let edge = self.edges.len();
+        let linked = from == to || self.slots[a].neighbors.contains(&to);
+        self.edges.push(Edge { from, to, weight, seq: self.next_seq });
+        self.next_seq += 1;
+        self.slots[a].outgoing.entry(to).or_default().push(edge);
+        self.slots[a].out_degree += 1;
+        self.slots[b].incoming.entry(from).or_default().push(edge);
+        if !linked {
+            self.link(a, b);
+        }
+        true
+    }
+
+    /// Whether `from` has a connection to `to`
+    pub fn has_edge(&self, from: &Uuid, to: &Uuid) -> bool {
+        self.index.get(from).is_some_and(|&slot| self.slots[slot].outgoing.contains_key(to))
+    }
+
+    /// The latest connection from `from` to `to`
+    pub fn find_edge_mut(&mut self, from: &Uuid, to: &Uuid) -> Option<&mut ConnectionEdge> {
+        let slot = *self.index.get(from)?;
+        let edge = *self.slots[slot].outgoing.get(to)?.last()?;
+        Some(&mut self.edges[edge].weight)
+    }
+
+    /// Connections from `id`, parallel ones included
+    pub fn out_degree(&self, id: &Uuid) -> usize {
+        self.index.get(id).map_or(0, |&slot| self.slots[slot].out_degree)
+    }
+
+    /// Connections from `id`
+    pub fn outgoing(&self, id: &Uuid) -> impl Iterator<Item = &ConnectionEdge> {
+        self.index.get(id)
+            .into_iter()
+            .flat_map(move |&slot| self.slots[slot].outgoing.values().flatten())
+            .map(move |&edge| &self.edges[edge].weight)
+    }
+
+    /// Number of agents by count of distinct neighbors, either direction
+    pub fn degree_distribution(&self) -> &BTreeMap<usize, usize> {
+        &self.degree_distribution
+    }
+
+    /// Sum of the agents' local clustering coefficients in slot order;
+    /// agents with fewer than two neighbors add nothing
+    pub fn clustering_sum(&self) -> f64 {
+        let mut sum = 0.0f64;
+        for clustering in self.slots.iter().filter_map(Slot::clustering) {
+            sum += clustering;
+        }
+        sum
+    }
+
+    /// Order and endpoints of the connections in `peers`, latest first.
+    /// Slots change as connections are removed; the order does not.
+    fn latest_first(&self, peers: &HashMap<Uuid, Vec<usize>>) -> Vec<(u64, Uuid, Uuid)> {
+        let mut edges: Vec<_> = peers.values()
+            .flatten()
+            .map(|&edge| {
+                let edge = &self.edges[edge];
+                (edge.seq, edge.from, edge.to)
+            })
+            .collect();
+        edges.sort_unstable_by_key(|&(seq, _, _)| std::cmp::Reverse(seq));
+        edges
+    }
+
+    fn remove_edge(&mut self, edge: usize) {
+        let removed = self.edges.swap_remove(edge);
+        let (a, b) = (self.index[&removed.from], self.index[&removed.to]);
+        Self::forget(&mut self.slots[a].outgoing, &removed.to, edge);
+        self.slots[a].out_degree -= 1;
+        Self::forget(&mut self.slots[b].incoming, &removed.from, edge);
+
+        // The last connection took the freed slot
+        let last = self.edges.len();
+        if edge < last {
+            let (from, to) = (self.edges[edge].from, self.edges[edge].to);
+            let (source, target) = (self.index[&from], self.index[&to]);
+            Self::renumber(&mut self.slots[source].outgoing, &to, last, edge);
+            Self::renumber(&mut self.slots[target].incoming, &from, last, edge);
+        }
+
+        let still_linked = removed.from == removed.to
+            || self.slots[a].outgoing.contains_key(&removed.to)
+            || self.slots[a].incoming.contains_key(&removed.to);
+        if !still_linked {
+            self.unlink(a, b);
+        }
+    }
+
+    fn forget(peers: &mut HashMap<Uuid, Vec<usize>>, peer: &Uuid, edge: usize) {
+        if let Some(edges) = peers.get_mut(peer) {
+            edges.retain(|&e| e != edge);
+            if edges.is_empty() {
+                peers.remove(peer);
+            }
+        }
+    }
+
+    fn renumber(peers: &mut HashMap<Uuid, Vec<usize>>, peer: &Uuid, from: usize, to: usize) {
+        if let Some(edge) = peers.get_mut(peer).and_then(|edges| edges.iter_mut().find(|e| **e == from)) {
+            *edge = to;
+        }
+    }
+
+    /// Make `a` and `b` neighbors, counting the triangles this closes
+    fn link(&mut self, a: usize, b: usize) {
+        let closed = self.common_neighbors(a, b);
+        for peer in &closed {
+            let slot = self.index[peer];
+            self.slots[slot].triangles += 1;
+        }
+        for (this, other) in [(a, b), (b, a)] {
+            let id = self.slots[other].node.id;
+            let degree = self.slots[this].neighbors.len();
+            self.count_degree(degree, -1);
+            self.count_degree(degree + 1, 1);
+            self.slots[this].neighbors.insert(id);
+            self.slots[this].triangles += closed.len();
+        }
+    }
+
+    /// Undo [`Self::link`]
+    fn unlink(&mut self, a: usize, b: usize) {
+        for (this, other) in [(a, b), (b, a)] {
+            let id = self.slots[other].node.id;
+            let degree = self.slots[this].neighbors.len();
+            self.count_degree(degree, -1);
+            self.count_degree(degree - 1, 1);
+            self.slots[this].neighbors.remove(&id);
+        }
+        let opened = self.common_neighbors(a, b);
+        for peer in &opened {
+            let slot = self.index[peer];
+            self.slots[slot].triangles -= 1;
+        }
+        self.slots[a].triangles -= opened.len();
+        self.slots[b].triangles -= opened.len();
+    }
+
+    /// Neighbors of both `a` and `b`, O(min deg)
+    fn common_neighbors(&self, a: usize, b: usize) -> Vec<Uuid> {
+        let (small, large) = if self.slots[a].neighbors.len() <= self.slots[b].neighbors.len() {
+            (&self.slots[a].neighbors, &self.slots[b].neighbors)
+        } else {
+            (&self.slots[b].neighbors, &self.slots[a].neighbors)
+        };
+        small.iter().filter(|peer| large.contains(peer)).copied().collect()
+    }
+
+    fn count_degree(&mut self, degree: usize, change: isize) {
+        let count = self.degree_distribution.entry(degree).or_insert(0);
+        *count = count.checked_add_signed(change).expect("degree count went negative");
+        if *count == 0 {
+            self.degree_distribution.remove(&degree);
+        }
+    }
+}
@@ -5,6 +5,7 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+use crate::specialization::Specialization;
 use crate::ContextWindow;
 
 /// Agent capability levels from L1 to L20
@@ -92,7 +93,9 @@ pub enum NetworkLayer {
 pub struct AgentProfile {
     pub id: Uuid,
     pub capability_level: AgentLevel,
-    pub specializations: Vec<String>,
+    /// Known category strengths the agent is placed with
+    #[serde(default)]
+    pub specialization: Specialization,
     pub performance_history: Vec<f32>,
     pub created_at: DateTime<Utc>,
     pub last_active: DateTime<Utc>,
@@ -104,7 +107,7 @@ impl AgentProfile {
         Self {
             id,
             capability_level: level,
-            specializations: Vec::new(),
+            specialization: Specialization::default(),
             performance_history: Vec::new(),
             created_at: now,
             last_active: now,
@@ -175,6 +178,9 @@ pub struct AssessmentResponse {
 pub struct MutualEvaluation {
     pub evaluator: Uuid,
     pub evaluated: Uuid,
+    /// Category of the question reviewed, if the review was of one
+    #[serde(default)]
+    pub category: Option<QuestionCategory>,
     pub scores: AssessmentScores,
     pub timestamp: DateTime<Utc>,
 }
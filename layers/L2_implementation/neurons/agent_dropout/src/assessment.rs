@@ -1,16 +1,18 @@
 //! Assessment question pool for agent evaluation
 
 use rand::seq::SliceRandom;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use uuid::Uuid;
 
 use crate::agent::{AssessmentQuestion, QuestionCategory, AgentLevel};
+use crate::scoring::AnswerKey;
 
 /// Assessment pool containing questions for evaluation
 pub struct AssessmentPool {
     questions: Vec<AssessmentQuestion>,
     by_category: HashMap<QuestionCategory, Vec<usize>>,
     by_difficulty: HashMap<u8, Vec<usize>>,
+    answer_keys: HashMap<Uuid, AnswerKey>,
 }
 
 impl Default for AssessmentPool {
@@ -25,9 +27,11 @@ impl AssessmentPool {
             questions: Vec::new(),
             by_category: HashMap::new(),
             by_difficulty: HashMap::new(),
+            answer_keys: HashMap::new(),
         };
         
         pool.initialize_questions();
+        pool.initialize_answer_keys();
         pool
     }
     
@@ -205,6 +209,42 @@ impl AssessmentPool {
         ]);
     }
     
+    /// Answer keys for questions with a known answer; the rest are judged
+    fn initialize_answer_keys(&mut self) {
+        self.set_answer_key("2, 4, 8, 16, ?", AnswerKey::Numeric { expected: 32.0, tolerance: 0.0 });
+        self.set_answer_key("1, 1, 2, 3, 5, 8, ?", AnswerKey::Numeric { expected: 13.0, tolerance: 0.0 });
+        self.set_answer_key(
+            "Find the pattern: OTTFFSS_",
+            AnswerKey::ExactMatch { accepted: vec!["E".to_string(), "Eight".to_string()] },
+        );
+        self.set_answer_key(
+            "A train leaves station A at 60 mph. Another leaves station B at 80 mph. If stations are 280 miles apart, when do they meet?",
+            AnswerKey::Numeric { expected: 2.0, tolerance: 0.05 },
+        );
+        self.set_answer_key(
+            "If all A are B, and all B are C, what can we conclude about A and C?",
+            AnswerKey::FreeText {
+                reference: Some("All A are C, because inclusion is transitive; not all C need be A".to_string()),
+            },
+        );
+        self.set_answer_key(
+            "You have 9 balls, one is heavier. Using a balance scale twice, how do you find it?",
+            AnswerKey::FreeText {
+                reference: Some(
+                    "Split the balls into three groups of three and weigh two groups; the heavier group, \
+                     or the unweighed one if balanced, holds the ball. Then weigh two balls from that group."
+                        .to_string(),
+                ),
+            },
+        );
+    }
+    
+    fn set_answer_key(&mut self, content: &str, key: AnswerKey) {
+        if let Some(question) = self.questions.iter().find(|q| q.content == content) {
+            self.answer_keys.insert(question.id, key);
+        }
+    }
+    
     fn add_questions(&mut self, questions: Vec<(&str, QuestionCategory, u8)>) {
         for (content, category, difficulty) in questions {
             let idx = self.questions.len();
@@ -259,6 +299,19 @@ impl AssessmentPool {
         self.questions.choose(&mut rng)
     }
     
+    /// How a question's answers are scored
+    pub fn answer_key(&self, question_id: Uuid) -> AnswerKey {
+        self.answer_keys.get(&question_id).cloned().unwrap_or_default()
+    }
+    
+    /// Number of questions at each difficulty
+    pub fn difficulty_distribution(&self) -> BTreeMap<u8, usize> {
+        self.by_difficulty
+            .iter()
+            .map(|(&difficulty, indices)| (difficulty, indices.len()))
+            .collect()
+    }
+    
     /// Get questions by category
     pub fn get_questions_by_category(&self, category: QuestionCategory) -> Vec<&AssessmentQuestion> {
         self.by_category
@@ -322,6 +375,25 @@ impl AssessmentPool {
         selected
     }
     
+    /// A short set spread over the pool's difficulty the way the pool is:
+    /// one random question from each of `count` equal slices of the pool
+    /// ordered by difficulty. The fraction answered then reads directly as
+    /// a quantile for `LevelCalibration`.
+    pub fn get_calibration_set(&self, count: usize) -> Vec<&AssessmentQuestion> {
+        let mut ordered: Vec<&AssessmentQuestion> = self.questions.iter().collect();
+        ordered.sort_by_key(|q| q.difficulty);
+        let count = count.min(ordered.len());
+        let mut rng = rand::thread_rng();
+        
+        (0..count)
+            .filter_map(|slice| {
+                let start = slice * ordered.len() / count;
+                let end = (slice + 1) * ordered.len() / count;
+                ordered[start..end].choose(&mut rng).copied()
+            })
+            .collect()
+    }
+    
     /// Generate a new random question (for diversity)
     pub fn generate_random_question(&self, level: AgentLevel) -> AssessmentQuestion {
         let templates = [
@@ -469,6 +541,27 @@ mod tests {
         // Test category retrieval
         let logical_questions = pool.get_questions_by_category(QuestionCategory::LogicalReasoning);
         assert!(!logical_questions.is_empty());
+        
+        let calibration_set = pool.get_calibration_set(8);
+        assert_eq!(calibration_set.len(), 8);
+        assert!(calibration_set.windows(2).all(|w| w[0].difficulty <= w[1].difficulty));
+        assert!(calibration_set[0].difficulty <= AgentLevel::L5);
+        assert!(calibration_set[7].difficulty >= AgentLevel::L19);
+        
+        let distribution = pool.difficulty_distribution();
+        assert_eq!(distribution.values().sum::<usize>(), pool.questions.len());
+        assert!(distribution.keys().all(|d| (1..=20).contains(d)));
+    }
+
+    #[test]
+    fn test_answer_keys() {
+        let pool = AssessmentPool::new();
+        let keyed = pool.questions.iter().filter(|q| pool.answer_keys.contains_key(&q.id)).count();
+        assert_eq!(keyed, 6);
+        
+        let doubling = pool.questions.iter().find(|q| q.content == "2, 4, 8, 16, ?").unwrap();
+        assert_eq!(pool.answer_key(doubling.id), AnswerKey::Numeric { expected: 32.0, tolerance: 0.0 });
+        assert_eq!(pool.answer_key(Uuid::new_v4()), AnswerKey::FreeText { reference: None });
     }
 
     #[test]
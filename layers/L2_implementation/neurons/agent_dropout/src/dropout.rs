@@ -3,21 +3,66 @@
 use chrono::{DateTime, Utc};
 use dashmap::DashMap;
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use uuid::Uuid;
 
 use crate::{
     agent::{AgentLevel, AgentProfile, AgentNeuron},
+    network::NetworkTopology,
+    specialization::SPECIALIST_SCORE,
     AgentError, AgentResult,
 };
 
 /// Decision result for agent dropout
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum DropoutDecision {
-    Keep(String),         // Reason to keep
-    Drop(String),         // Reason to drop
-    Monitor(String),      // Continue monitoring
+    Keep(DropoutRationale),         // Reason to keep
+    Drop(DropoutRationale),         // Reason to drop
+    Monitor(DropoutRationale),      // Continue monitoring
+}
+
+impl DropoutDecision {
+    pub fn rationale(&self) -> &DropoutRationale {
+        match self {
+            DropoutDecision::Keep(rationale)
+            | DropoutDecision::Drop(rationale)
+            | DropoutDecision::Monitor(rationale) => rationale,
+        }
+    }
+}
+
+/// What a dropout decision was based on
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DropoutRationale {
+    pub agent_id: Uuid,
+    pub reason: String,
+    /// Success rate over the agent's evaluations, 0 without any
+    pub score: f32,
+    /// Upper end of the Wilson interval on `score`, raised for sole
+    /// specialists. An agent is only dropped once this is below `threshold`,
+    /// i.e. once it is confidently underperforming however few evaluations
+    /// it has had
+    pub bound: f32,
+    /// Evaluations recorded for the agent
+    pub samples: u64,
+    pub threshold: f32,
+}
+
+/// Wilson score interval on the success rate `successes / samples`, `z`
+/// standard deviations wide; `(0, 1)` without samples
+pub fn wilson_interval(successes: f64, samples: u64, z: f64) -> (f32, f32) {
+    if samples == 0 {
+        return (0.0, 1.0);
+    }
+    let n = samples as f64;
+    let p = (successes / n).clamp(0.0, 1.0);
+    let z2 = z * z;
+    let center = p + z2 / (2.0 * n);
+    let margin = z * (p * (1.0 - p) / n + z2 / (4.0 * n * n)).sqrt();
+    let denominator = 1.0 + z2 / n;
+    (((center - margin) / denominator).max(0.0) as f32, ((center + margin) / denominator).min(1.0) as f32)
 }
 
 /// Dropout controller for managing agent quality
@@ -32,12 +77,47 @@ pub struct DropoutConfig {
     pub evaluation_interval: std::time::Duration,
     /// Minimum number of agents to maintain
     pub minimum_agent_count: usize,
-    /// Grace period for new agents before they can be dropped
-    pub grace_period: std::time::Duration,
+    /// Evaluations a new agent has before it can be dropped
+    #[serde(default = "default_grace_evaluations")]
+    pub grace_evaluations: u64,
+    /// Success rate an agent must be confidently below to be dropped
+    #[serde(default = "default_success_threshold")]
+    pub success_threshold: f32,
+    /// Width of the confidence interval on success rates, in standard
+    /// deviations (1.96 for 95%)
+    #[serde(default = "default_confidence_z")]
+    pub confidence_z: f32,
     /// Minimum number of agents (alias)
     pub min_agents: usize,
     /// Maximum number of agents allowed
     pub max_agents: usize,
+    /// Category average at which an agent counts as a strong specialist
+    #[serde(default = "default_specialist_threshold")]
+    pub specialist_threshold: f32,
+    /// The only strong specialist in some category is ranked and checked on
+    /// its score times `1 + specialist_protection`
+    #[serde(default = "default_specialist_protection")]
+    pub specialist_protection: f32,
+}
+
+fn default_specialist_threshold() -> f32 {
+    SPECIALIST_SCORE
+}
+
+fn default_specialist_protection() -> f32 {
+    0.5
+}
+
+fn default_grace_evaluations() -> u64 {
+    10
+}
+
+fn default_success_threshold() -> f32 {
+    0.5
+}
+
+fn default_confidence_z() -> f32 {
+    1.96
 }
 
 impl Default for DropoutConfig {
@@ -46,9 +126,13 @@ impl Default for DropoutConfig {
             dropout_threshold: 0.1, // Bottom 10%
             evaluation_interval: std::time::Duration::from_secs(300), // 5 minutes
             minimum_agent_count: 10,
-            grace_period: std::time::Duration::from_secs(600), // 10 minutes
+            grace_evaluations: default_grace_evaluations(),
+            success_threshold: default_success_threshold(),
+            confidence_z: default_confidence_z(),
             min_agents: 10,
             max_agents: 1000,
+            specialist_threshold: default_specialist_threshold(),
+            specialist_protection: default_specialist_protection(),
         }
     }
 }
@@ -117,13 +201,21 @@ pub struct DropoutOrchestrator {
     performance_history: Arc<DashMap<Uuid, Vec<PerformanceSnapshot>>>,
     replacement_pool: Arc<AgentReplacementPool>,
     last_evaluation: Arc<RwLock<DateTime<Utc>>>,
+    /// Dropped agents by age bucket, see `age_bucket`
+    dropped_ages: Arc<RwLock<BTreeMap<u64, usize>>>,
+    /// Where agents' specializations are learned; without it no agent is
+    /// protected as a specialist
+    topology: Option<Arc<NetworkTopology>>,
 }
 
 struct AgentState {
     agent: Box<dyn AgentNeuron>,
-    joined_at: DateTime<Utc>,
     last_activity: DateTime<Utc>,
     dropout_warnings: u32,
+    /// Evaluations recorded, which is also the agent's age
+    evaluations: u64,
+    /// Sum of evaluation scores
+    successes: f64,
 }
 
 #[derive(Clone, Serialize, Deserialize)]
@@ -145,9 +237,13 @@ impl DropoutOrchestrator {
             dropout_threshold: quality_threshold,
             evaluation_interval: idle_timeout,
             minimum_agent_count: 10,
-            grace_period: std::time::Duration::from_secs(600),
+            grace_evaluations: default_grace_evaluations(),
+            success_threshold: default_success_threshold(),
+            confidence_z: default_confidence_z(),
             min_agents: 10,
             max_agents: 1000,
+            specialist_threshold: default_specialist_threshold(),
+            specialist_protection: default_specialist_protection(),
         };
         
         Self::with_config(config)
@@ -161,17 +257,27 @@ impl DropoutOrchestrator {
             performance_history: Arc::new(DashMap::new()),
             replacement_pool: Arc::new(AgentReplacementPool::new(20)),
             last_evaluation: Arc::new(RwLock::new(Utc::now())),
+            dropped_ages: Arc::new(RwLock::new(BTreeMap::new())),
+            topology: None,
         }
     }
     
+    /// Protect sole specialists according to the specializations learned
+    /// in `topology`
+    pub fn with_topology(mut self, topology: Arc<NetworkTopology>) -> Self {
+        self.topology = Some(topology);
+        self
+    }
+    
     /// Register a new agent
     pub async fn register_agent(&self, agent: Box<dyn AgentNeuron>) -> AgentResult<()> {
         let agent_id = agent.id();
         let state = AgentState {
             agent,
-            joined_at: Utc::now(),
             last_activity: Utc::now(),
             dropout_warnings: 0,
+            evaluations: 0,
+            successes: 0.0,
         };
         
         self.active_agents.insert(agent_id, state);
@@ -180,6 +286,16 @@ impl DropoutOrchestrator {
         Ok(())
     }
     
+    /// Record one evaluation of an agent, `score` being how successful it
+    /// was from 0 to 1
+    pub async fn record_evaluation(&self, agent_id: Uuid, score: f32) -> AgentResult<()> {
+        let mut state = self.active_agents.get_mut(&agent_id).ok_or(AgentError::NotFound(agent_id))?;
+        state.evaluations += 1;
+        state.successes += score.clamp(0.0, 1.0) as f64;
+        state.last_activity = Utc::now();
+        Ok(())
+    }
+    
     /// Main health check cycle
     pub async fn health_check_cycle(&self) -> AgentResult<DropoutReport> {
         // Check if it's time to evaluate
@@ -192,6 +308,7 @@ impl DropoutOrchestrator {
                 dropped_agents: vec![],
                 replaced_agents: vec![],
                 warnings_issued: 0,
+                decisions: vec![],
             });
         }
         
@@ -199,22 +316,29 @@ impl DropoutOrchestrator {
         drop(last_eval);
         
         // Collect performance metrics
-        let rankings = self.calculate_agent_rankings().await;
-        
-        // Identify bottom performers
-        let dropout_candidates = self.identify_bottom_performers(&rankings);
+        let mut decisions = self.calculate_agent_decisions().await;
+        let allowance = self.dropout_allowance(decisions.len());
         
         // Process dropouts
         let mut report = DropoutReport {
-            evaluated_agents: rankings.len(),
+            evaluated_agents: decisions.len(),
             dropped_agents: vec![],
             replaced_agents: vec![],
             warnings_issued: 0,
+            decisions: vec![],
         };
         
-        for agent_id in dropout_candidates {
-            // Check grace period
-            if self.is_in_grace_period(agent_id).await {
+        for decision in decisions.iter_mut() {
+            let DropoutDecision::Drop(rationale) = decision else {
+                continue;
+            };
+            let agent_id = rationale.agent_id;
+            
+            // Confidently underperforming, but over this cycle's limit
+            if report.dropped_agents.len() >= allowance {
+                let mut rationale = rationale.clone();
+                rationale.reason.push_str(", dropout limit reached");
+                *decision = DropoutDecision::Monitor(rationale);
                 self.issue_warning(agent_id).await;
                 report.warnings_issued += 1;
                 continue;
@@ -234,11 +358,14 @@ impl DropoutOrchestrator {
         // Ensure replacement pool is maintained
         self.replacement_pool.ensure_minimum_pool().await;
         
+        report.decisions = decisions;
         Ok(report)
     }
     
-    async fn calculate_agent_rankings(&self) -> Vec<(Uuid, f32)> {
-        let mut rankings = Vec::new();
+    /// Decide on every agent, the least likely to be performing first
+    async fn calculate_agent_decisions(&self) -> Vec<DropoutDecision> {
+        let mut decisions = Vec::new();
+        let specialists = self.sole_specialists().await;
         
         for entry in self.active_agents.iter() {
             let agent_id = *entry.key();
@@ -249,7 +376,7 @@ impl DropoutOrchestrator {
             let snapshot = PerformanceSnapshot {
                 timestamp: Utc::now(),
                 overall_score: performance,
-                task_count: 0, // Would come from performance tracker
+                task_count: state.evaluations,
                 peer_rating: performance, // Simplified
             };
             
@@ -262,42 +389,72 @@ impl DropoutOrchestrator {
                 }
             }
             
-            rankings.push((agent_id, performance));
+            decisions.push(self.decide(agent_id, state, &specialists));
         }
         
-        // Sort by performance (ascending, so worst performers first)
-        rankings.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+        // Sort by bound (ascending, so worst performers first)
+        decisions.sort_by(|a, b| a.rationale().bound.partial_cmp(&b.rationale().bound).unwrap());
         
-        rankings
+        decisions
     }
     
-    fn identify_bottom_performers(&self, rankings: &[(Uuid, f32)]) -> Vec<Uuid> {
-        let total_agents = rankings.len();
-        
-        // Don't drop if we're at minimum agent count
-        if total_agents <= self.config.minimum_agent_count {
-            return vec![];
+    /// Drop an agent only once it is past its grace period and even the
+    /// optimistic end of its success rate interval is below the threshold.
+    /// Few evaluations widen the interval, so newcomers are not dropped on
+    /// the noise of a small sample.
+    fn decide(&self, agent_id: Uuid, state: &AgentState, specialists: &HashSet<Uuid>) -> DropoutDecision {
+        let samples = state.evaluations;
+        let score = if samples > 0 { (state.successes / samples as f64) as f32 } else { 0.0 };
+        let (_, upper) = wilson_interval(state.successes, samples, self.config.confidence_z as f64);
+        let bound = self.protected_score(agent_id, upper, specialists);
+        let threshold = self.config.success_threshold;
+        let rationale = |reason: String| DropoutRationale { agent_id, reason, score, bound, samples, threshold };
+        
+        if samples < self.config.grace_evaluations {
+            DropoutDecision::Monitor(rationale(format!(
+                "in grace period, {} of {} evaluations",
+                samples, self.config.grace_evaluations
+            )))
+        } else if bound < threshold {
+            DropoutDecision::Drop(rationale(format!(
+                "success rate {:.2} over {} evaluations, at most {:.2} against {:.2} required",
+                score, samples, bound, threshold
+            )))
+        } else {
+            DropoutDecision::Keep(rationale(format!(
+                "success rate {:.2} over {} evaluations, possibly {:.2} against {:.2} required",
+                score, samples, bound, threshold
+            )))
+        }
+    }
+    
+    /// Agents that are the only strong specialist in some category
+    async fn sole_specialists(&self) -> HashSet<Uuid> {
+        match &self.topology {
+            Some(topology) => topology.sole_specialists(self.config.specialist_threshold).await,
+            None => HashSet::new(),
         }
-        
-        // Calculate how many to drop
-        let dropout_count = ((total_agents as f32 * self.config.dropout_threshold) as usize)
-            .min(total_agents - self.config.minimum_agent_count);
-        
-        rankings.iter()
-            .take(dropout_count)
-            .map(|(id, _)| *id)
-            .collect()
     }
     
-    async fn is_in_grace_period(&self, agent_id: Uuid) -> bool {
-        if let Some(state) = self.active_agents.get(&agent_id) {
-            let age = Utc::now().signed_duration_since(state.joined_at);
-            age.to_std().unwrap() < self.config.grace_period
+    fn protected_score(&self, agent_id: Uuid, score: f32, specialists: &HashSet<Uuid>) -> f32 {
+        if specialists.contains(&agent_id) {
+            score * (1.0 + self.config.specialist_protection)
         } else {
-            false
+            score
         }
     }
     
+    /// How many agents one cycle may drop at most
+    fn dropout_allowance(&self, total_agents: usize) -> usize {
+        // Don't drop if we're at minimum agent count
+        if total_agents <= self.config.minimum_agent_count {
+            return 0;
+        }
+        
+        ((total_agents as f32 * self.config.dropout_threshold) as usize)
+            .min(total_agents - self.config.minimum_agent_count)
+    }
+    
     async fn issue_warning(&self, agent_id: Uuid) {
         if let Some(mut state) = self.active_agents.get_mut(&agent_id) {
             state.dropout_warnings += 1;
@@ -317,6 +474,7 @@ impl DropoutOrchestrator {
             
             // Clean up performance history
             self.performance_history.remove(&agent_id);
+            *self.dropped_ages.write().await.entry(age_bucket(state.evaluations)).or_default() += 1;
             
             Ok(())
         } else {
@@ -341,7 +499,10 @@ impl DropoutOrchestrator {
     }
     
     /// Check if an agent should be dropped
-    pub async fn should_dropout(&self, _profile: &AgentProfile, quality_score: f32) -> bool {
+    pub async fn should_dropout(&self, profile: &AgentProfile, quality_score: f32) -> bool {
+        let specialists = self.sole_specialists().await;
+        let quality_score = self.protected_score(profile.id, quality_score, &specialists);
+        
         // Check quality threshold
         if quality_score < self.config.dropout_threshold {
             return true;
@@ -353,7 +514,8 @@ impl DropoutOrchestrator {
             // Drop lowest performers
             let mut scores: Vec<(Uuid, f32)> = Vec::new();
             for entry in self.active_agents.iter() {
-                scores.push((*entry.key(), entry.value().agent.performance_score()));
+                let agent_id = *entry.key();
+                scores.push((agent_id, self.protected_score(agent_id, entry.value().agent.performance_score(), &specialists)));
             }
             scores.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
             
@@ -406,10 +568,20 @@ impl DropoutOrchestrator {
             average_performance: avg_performance,
             dropout_rate: self.config.dropout_threshold,
             last_evaluation: *self.last_evaluation.read().await,
+            dropped_age_distribution: self.dropped_ages.read().await.clone(),
         }
     }
 }
 
+/// Age bucket of an agent with `evaluations`: 0, then the power of two at
+/// or below it
+fn age_bucket(evaluations: u64) -> u64 {
+    match evaluations {
+        0 => 0,
+        n => 1 << n.ilog2(),
+    }
+}
+
 /// Report of a dropout cycle
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DropoutReport {
@@ -417,6 +589,10 @@ pub struct DropoutReport {
     pub dropped_agents: Vec<Uuid>,
     pub replaced_agents: Vec<Uuid>,
     pub warnings_issued: usize,
+    /// Decision on every evaluated agent, the least likely to be performing
+    /// first
+    #[serde(default)]
+    pub decisions: Vec<DropoutDecision>,
 }
 
 /// Network statistics
@@ -427,6 +603,10 @@ pub struct NetworkStats {
     pub average_performance: f32,
     pub dropout_rate: f32,
     pub last_evaluation: DateTime<Utc>,
+    /// Agents dropped so far by evaluations at removal, bucketed by the
+    /// power of two at or below (0, 1, 2, 4, 8, ...)
+    #[serde(default)]
+    pub dropped_age_distribution: BTreeMap<u64, usize>,
 }
 
 /// Evolutionary optimizer for long-term network improvement
@@ -557,6 +737,17 @@ mod tests {
     // }
 
     #[test]
+    fn test_wilson_interval_narrows_with_samples() {
+        assert_eq!(wilson_interval(0.0, 0, 1.96), (0.0, 1.0));
+        let (low, high) = wilson_interval(6.0, 10, 1.96);
+        assert!((low - 0.3127).abs() < 1e-3 && (high - 0.8318).abs() < 1e-3, "{} {}", low, high);
+        let (low, high) = wilson_interval(600.0, 1000, 1.96);
+        assert!(low > 0.56 && high < 0.64, "{} {}", low, high);
+        assert_eq!(age_bucket(0), 0);
+        assert_eq!((age_bucket(1), age_bucket(7), age_bucket(8), age_bucket(300)), (1, 4, 8, 256));
+    }
+
+        #[test]
     fn test_evolutionary_optimizer() {
         let mut optimizer = EvolutionaryOptimizer::new(0.2, 0.1);
         
@@ -566,6 +757,7 @@ mod tests {
             average_performance: 0.5,
             dropout_rate: 0.1,
             last_evaluation: Utc::now(),
+            dropped_age_distribution: BTreeMap::new(),
         };
         
         // Simulate improving performance
@@ -4,12 +4,15 @@ use async_trait::async_trait;
 use chrono::{DateTime, Utc};
 use dashmap::DashMap;
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
 use std::sync::Arc;
 use uuid::Uuid;
 
 use crate::{
-    agent::{AgentLevel, AssessmentQuestion, AssessmentResponse, MutualEvaluation, AssessmentScores},
-    AgentResult,
+    agent::{AgentLevel, AssessmentQuestion, AssessmentResponse, MutualEvaluation, AssessmentScores, QuestionCategory},
+    assessment::AssessmentPool,
+    scoring::{self, AnswerJudge, AnswerKey, AnswerScore, HeuristicJudge, RubricConfig},
+    AgentError, AgentResult,
 };
 
 /// Evaluation protocol for mutual agent assessment
@@ -58,6 +61,8 @@ pub struct LevelEstimate {
 pub struct EvaluationEngine {
     evaluations: Arc<DashMap<Uuid, Vec<MutualEvaluation>>>,
     active_agents: Arc<DashMap<Uuid, AgentInfo>>,
+    judge: Arc<dyn AnswerJudge>,
+    rubrics: RubricConfig,
 }
 
 #[derive(Clone)]
@@ -76,9 +81,16 @@ impl Default for EvaluationEngine {
 
 impl EvaluationEngine {
     pub fn new() -> Self {
+        Self::with_judge(Arc::new(HeuristicJudge), RubricConfig::default())
+    }
+    
+    /// Engine grading free text with `judge`, weighting criteria by `rubrics`
+    pub fn with_judge(judge: Arc<dyn AnswerJudge>, rubrics: RubricConfig) -> Self {
         Self {
             evaluations: Arc::new(DashMap::new()),
             active_agents: Arc::new(DashMap::new()),
+            judge,
+            rubrics,
         }
     }
     
@@ -108,11 +120,59 @@ impl EvaluationEngine {
         MutualEvaluation {
             evaluator: evaluator_id,
             evaluated: evaluatee_id,
+            category: Some(question.category),
             scores,
             timestamp: Utc::now(),
         }
     }
     
+    /// Score one answer against its key
+    pub async fn score_answer(
+        &self,
+        question: &AssessmentQuestion,
+        key: &AnswerKey,
+        response: &AssessmentResponse,
+    ) -> AgentResult<AnswerScore> {
+        scoring::score_answer(self.judge.as_ref(), &self.rubrics, question, key, response).await
+    }
+    
+    /// Score a set of answers, keyed by `pool`, into an overall result
+    pub async fn evaluate(
+        &self,
+        pool: &AssessmentPool,
+        answers: &[(AssessmentQuestion, AssessmentResponse)],
+    ) -> AgentResult<EvaluationResult> {
+        if answers.is_empty() {
+            return Err(AgentError::AssessmentFailed("No answers to evaluate".to_string()));
+        }
+        
+        let mut scores = Vec::with_capacity(answers.len());
+        for (question, response) in answers {
+            let key = pool.answer_key(question.id);
+            scores.push(self.score_answer(question, &key, response).await?);
+        }
+        
+        let mut by_category: HashMap<QuestionCategory, Vec<f32>> = HashMap::new();
+        for score in &scores {
+            by_category.entry(score.category).or_default().push(score.score);
+        }
+        let category_scores = by_category
+            .into_iter()
+            .map(|(category, values)| (category, mean(&values)))
+            .collect();
+        
+        let overall_score = mean(&scores.iter().map(|s| s.score).collect::<Vec<_>>());
+        let max_difficulty = scores.iter().map(|s| s.difficulty).max().unwrap_or(1);
+        
+        Ok(EvaluationResult {
+            overall_score,
+            level_estimate: LevelCalibration::from_pool(pool).level_for(overall_score, max_difficulty),
+            category_scores,
+            time_efficiency: mean(&scores.iter().map(|s| s.time_efficiency).collect::<Vec<_>>()),
+            consistency_score: consistency(&scores),
+        })
+    }
+    
     fn calculate_scores(
         &self,
         _question: &AssessmentQuestion,
@@ -262,6 +322,72 @@ impl EvaluationEngine {
     }
 }
 
+fn mean(values: &[f32]) -> f32 {
+    if values.is_empty() {
+        return 0.0;
+    }
+    values.iter().sum::<f32>() / values.len() as f32
+}
+
+/// Agreement between scores on questions of equal difficulty: 1.0 when
+/// repeated questions score the same, 0.0 at the largest possible spread.
+/// Without repeated difficulties there is nothing to disagree, so 1.0.
+pub fn consistency(scores: &[AnswerScore]) -> f32 {
+    let mut by_difficulty: BTreeMap<u8, Vec<f32>> = BTreeMap::new();
+    for score in scores {
+        by_difficulty.entry(score.difficulty).or_default().push(score.score);
+    }
+    
+    let variances: Vec<f32> = by_difficulty
+        .values()
+        .filter(|group| group.len() >= 2)
+        .map(|group| {
+            let m = mean(group);
+            mean(&group.iter().map(|s| (s - m) * (s - m)).collect::<Vec<_>>())
+        })
+        .collect();
+    if variances.is_empty() {
+        return 1.0;
+    }
+    
+    // Scores lie in 0..=1, so the standard deviation is at most 0.5
+    (1.0 - 2.0 * mean(&variances).sqrt()).clamp(0.0, 1.0)
+}
+
+/// Maps an overall score to a level using the pool's difficulty spread: a
+/// score is read as the fraction of the pool's questions the agent handles,
+/// and the level is the difficulty at that quantile. Answers only show
+/// ability up to the questions asked, so the level is capped two above the
+/// hardest question answered.
+#[derive(Debug, Clone)]
+pub struct LevelCalibration {
+    /// Difficulty of every question in the pool, ascending
+    difficulties: Vec<u8>,
+}
+
+impl LevelCalibration {
+    pub fn from_pool(pool: &AssessmentPool) -> Self {
+        let difficulties = pool
+            .difficulty_distribution()
+            .into_iter()
+            .flat_map(|(difficulty, count)| std::iter::repeat_n(difficulty, count))
+            .collect();
+        Self { difficulties }
+    }
+    
+    pub fn level_for(&self, score: f32, max_difficulty: u8) -> AgentLevel {
+        let score = score.clamp(0.0, 1.0);
+        let level = if self.difficulties.is_empty() {
+            (score * 19.0).round() as u8 + 1
+        } else {
+            let index = (score * (self.difficulties.len() - 1) as f32).round() as usize;
+            self.difficulties[index]
+        };
+        let cap = max_difficulty.saturating_add(2).min(20);
+        AgentLevel::from_value(level.clamp(1, cap)).unwrap()
+    }
+}
+
 /// Bayesian level estimator for more sophisticated estimation
 pub struct BayesianLevelEstimator {
     prior_distribution: Vec<f32>, // Prior beliefs about level distribution
@@ -396,6 +522,106 @@ mod tests {
         assert!(estimate.confidence <= 1.0);
     }
 
+    fn answer(pool: &AssessmentPool, content: &str, difficulty: u8, text: &str) -> (AssessmentQuestion, AssessmentResponse) {
+        let question = pool
+            .get_questions_by_category(QuestionCategory::LogicalReasoning)
+            .into_iter()
+            .chain(pool.get_questions_by_category(QuestionCategory::PatternRecognition))
+            .find(|q| q.content == content)
+            .cloned()
+            .unwrap_or_else(|| AssessmentQuestion {
+                id: Uuid::new_v4(),
+                category: QuestionCategory::SystemsThinking,
+                difficulty: AgentLevel::from_value(difficulty).unwrap(),
+                content: content.to_string(),
+                time_limit: None,
+            });
+        let response = AssessmentResponse {
+            question_id: question.id,
+            answer: text.to_string(),
+            time_taken: std::time::Duration::from_secs(25),
+            confidence: 0.5,
+        };
+        (question, response)
+    }
+
+    #[tokio::test]
+    async fn test_rubric_evaluation_ordered_by_quality() {
+        let pool = AssessmentPool::new();
+        let engine = EvaluationEngine::new();
+        let sets = [
+            vec![
+                answer(&pool, "2, 4, 8, 16, ?", 2, "no idea"),
+                answer(&pool, "1, 1, 2, 3, 5, 8, ?", 3, "7"),
+                answer(&pool, "If all A are B, and all B are C, what can we conclude about A and C?", 3, "unclear"),
+            ],
+            vec![
+                answer(&pool, "2, 4, 8, 16, ?", 2, "32"),
+                answer(&pool, "1, 1, 2, 3, 5, 8, ?", 3, "12"),
+                answer(&pool, "If all A are B, and all B are C, what can we conclude about A and C?", 3, "All A are C"),
+            ],
+            vec![
+                answer(&pool, "2, 4, 8, 16, ?", 2, "32, because each term doubles the one before it"),
+                answer(&pool, "1, 1, 2, 3, 5, 8, ?", 3, "13, since each term is the sum of the previous two, so 5 + 8 = 13"),
+                answer(
+                    &pool,
+                    "If all A are B, and all B are C, what can we conclude about A and C?",
+                    3,
+                    "All A are C, because inclusion is transitive: every A is a B and every B is a C, \
+                     therefore every A is a C. The converse does not hold, so not all C need be A.",
+                ),
+            ],
+        ];
+        
+        let mut results = Vec::new();
+        for set in &sets {
+            results.push(engine.evaluate(&pool, set).await.unwrap());
+        }
+        
+        assert!(results.windows(2).all(|w| w[0].overall_score < w[1].overall_score));
+        assert!(results.windows(2).all(|w| w[0].level_estimate <= w[1].level_estimate));
+        for result in &results {
+            assert_eq!(result.category_scores.len(), 2);
+            assert!(result.level_estimate.value() <= 5);
+        }
+        let best = &results[2];
+        assert!(best.category_scores[&QuestionCategory::PatternRecognition] > results[0].category_scores[&QuestionCategory::PatternRecognition]);
+        assert!(best.time_efficiency > 0.9);
+    }
+
+    #[tokio::test]
+    async fn test_consistency_across_equal_difficulty() {
+        let pool = AssessmentPool::new();
+        let engine = EvaluationEngine::new();
+        let good = "A gossip protocol spreads membership changes, because every node periodically \
+                    merges state with random peers, so views converge; therefore consistency is eventual.";
+        
+        let steady = [answer(&pool, "Design a membership protocol", 9, good), answer(&pool, "Design a membership protocol", 9, good)];
+        let erratic = [answer(&pool, "Design a membership protocol", 9, good), answer(&pool, "Design a membership protocol", 9, "no")];
+        let single = [answer(&pool, "Design a membership protocol", 9, "no")];
+        
+        let steady = engine.evaluate(&pool, &steady).await.unwrap().consistency_score;
+        let erratic = engine.evaluate(&pool, &erratic).await.unwrap().consistency_score;
+        let single = engine.evaluate(&pool, &single).await.unwrap().consistency_score;
+        assert!(steady > erratic, "{} <= {}", steady, erratic);
+        assert!(steady > 0.9);
+        assert_eq!(single, 1.0);
+        
+        assert!(engine.evaluate(&pool, &[]).await.is_err());
+    }
+
+    #[test]
+    fn test_level_calibration() {
+        let calibration = LevelCalibration::from_pool(&AssessmentPool::new());
+        let levels: Vec<u8> = (0..=10).map(|i| calibration.level_for(i as f32 / 10.0, 20).value()).collect();
+        assert!(levels.windows(2).all(|w| w[0] <= w[1]), "{:?}", levels);
+        assert!(levels[0] <= 3);
+        assert_eq!(levels[10], 20);
+        
+        // Capped by the hardest question answered
+        assert_eq!(calibration.level_for(1.0, 4).value(), 6);
+    }
+
     #[test]
     fn test_bayesian_estimator() {
         let estimator = BayesianLevelEstimator::new();
@@ -406,6 +632,7 @@ mod tests {
             evaluations.push(MutualEvaluation {
                 evaluator: Uuid::new_v4(),
                 evaluated: Uuid::new_v4(),
+                category: None,
                 scores: AssessmentScores {
                     accuracy: 0.7,
                     reasoning: 0.8,
@@ -6,17 +6,26 @@
 //! - Performance-based dropout
 //! - Continuous improvement cycles
 
+mod adjacency;
 pub mod agent;
 pub mod assessment;
 pub mod dropout;
 pub mod evaluation;
 pub mod network;
+pub mod persistence;
+pub mod scheduler;
+pub mod scoring;
+pub mod specialization;
 
 pub use agent::{AgentLevel, AgentProfile, AgentCapability, NetworkLayer, AssessmentResponse};
 pub use assessment::{AssessmentPool, QuestionValidator};
-pub use dropout::{DropoutController, DropoutDecision};
-pub use evaluation::{EvaluationEngine, EvaluationResult};
-pub use network::{NetworkTopology, NetworkStats, LayerStats};
+pub use dropout::{DropoutController, DropoutDecision, DropoutRationale};
+pub use evaluation::{EvaluationEngine, EvaluationResult, LevelCalibration};
+pub use network::{NetworkTopology, NetworkStats, LayerStats, ConnectionDecay};
+pub use persistence::{FileTopologyStore, TopologyChange, TopologySnapshot, TopologyStore};
+pub use scheduler::{AssessmentScheduler, FileScheduleStore, Reassessment, ScheduleConfig, ScheduleEntry, ScheduleState, ScheduleStore};
+pub use scoring::{AnswerJudge, AnswerKey, AnswerScore, ClaudeJudge, HeuristicJudge, RubricConfig, RubricWeights};
+pub use specialization::{CategoryDistribution, Specialist, Specialization};
 
 // Re-export common types
 pub use agent::QuestionCategory;
@@ -37,6 +46,8 @@ pub enum AgentError {
     NetworkError(String),
     #[error("Assessment failed: {0}")]
     AssessmentFailed(String),
+    #[error("Persistence error: {0}")]
+    Persistence(String),
 }
 
 /// Result type for agent operations
@@ -120,6 +131,7 @@ mod tests {
 #[cfg(test)]
 mod integration_tests {
     use super::*;
+    use crate::agent::AssessmentQuestion;
     use std::time::Duration;
     use uuid::Uuid;
     
@@ -128,7 +140,7 @@ mod integration_tests {
         // Create components
         let topology = NetworkTopology::new();
         let assessment_pool = AssessmentPool::new();
-        let _evaluation_engine = EvaluationEngine::new();
+        let evaluation_engine = EvaluationEngine::new();
         let dropout_controller = DropoutController::new(
             1024 * 1024 * 10, // 10MB
             Duration::from_secs(300),
@@ -151,28 +163,25 @@ mod integration_tests {
         assert!(!questions.is_empty());
         
         // Create mock responses
-        let _responses: Vec<AssessmentResponse> = questions.iter().map(|q| {
-            AssessmentResponse {
+        let answers: Vec<(AssessmentQuestion, AssessmentResponse)> = questions.iter().map(|q| {
+            let response = AssessmentResponse {
                 question_id: q.id,
                 answer: "Test answer".to_string(),
                 time_taken: Duration::from_secs(30),
                 confidence: 0.8,
-            }
+            };
+            (q.clone(), response)
         }).collect();
         
-        // Evaluate - for now just create a dummy evaluation result
-        let result = EvaluationResult {
-            overall_score: 0.8,
-            level_estimate: AgentLevel::L5,
-            category_scores: std::collections::HashMap::new(),
-            time_efficiency: 0.9,
-            consistency_score: 0.85,
-        };
+        // Evaluate
+        let result: EvaluationResult = evaluation_engine.evaluate(&assessment_pool, &answers).await.unwrap();
         assert!(result.overall_score >= 0.0 && result.overall_score <= 1.0);
+        assert!(result.level_estimate <= AgentLevel::L9);
+        assert!(!result.category_scores.is_empty());
         
         // Check dropout decision
         let should_dropout = dropout_controller.should_dropout(&profile, result.overall_score).await;
-        assert!(!should_dropout); // Should not dropout with reasonable score
+        assert_eq!(should_dropout, result.overall_score < 0.7);
     }
     
     #[tokio::test]
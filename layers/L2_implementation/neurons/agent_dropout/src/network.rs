@@ -1,30 +1,49 @@
 //! Network topology and agent placement management
 
+use chrono::{DateTime, Utc};
 use dashmap::DashMap;
-use petgraph::graph::{DiGraph, NodeIndex};
-use petgraph::visit::EdgeRef;
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::Write;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use uuid::Uuid;
 
-use crate::agent::{AgentLevel, AgentProfile, NetworkLayer};
-use crate::ContextWindow;
+use crate::adjacency::AgentGraph;
+use crate::agent::{AgentLevel, AgentProfile, MutualEvaluation, NetworkLayer, QuestionCategory};
+use crate::evaluation::EvaluationResult;
+use crate::persistence::{PersistedConnection, TopologyChange, TopologySnapshot, TopologyStore};
+use crate::specialization::{self, CategoryDistribution, Specialist, Specialization};
+use crate::{AgentResult, ContextWindow};
 
 /// Network statistics
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct NetworkStats {
     pub total_agents: usize,
     pub total_connections: usize,
     pub average_connectivity: f32,
     pub layer_distribution: std::collections::HashMap<NetworkLayer, usize>,
+    /// Number of agents by count of distinct neighbors, either direction
+    #[serde(default)]
+    pub degree_distribution: BTreeMap<usize, usize>,
+    /// Mean local clustering coefficient of the undirected graph; agents with
+    /// fewer than two neighbors count as 0
+    #[serde(default)]
+    pub clustering_coefficient: f32,
+    /// Category averages across the agents scored in each category
+    #[serde(default)]
+    pub specialization_distribution: HashMap<QuestionCategory, CategoryDistribution>,
 }
 
 /// Network topology manager
+///
+/// Connections are kept per agent, so connecting, finding a connection and
+/// placing an agent do not grow with the network, and statistics take one
+/// pass over the agents.
 pub struct NetworkTopology {
-    graph: Arc<RwLock<DiGraph<AgentNode, ConnectionEdge>>>,
-    agent_indices: Arc<DashMap<Uuid, NodeIndex>>,
+    graph: Arc<RwLock<AgentGraph>>,
     layer_groups: Arc<DashMap<NetworkLayer, Vec<Uuid>>>,
+    store: Option<Arc<dyn TopologyStore>>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -33,6 +52,9 @@ pub struct AgentNode {
     pub level: AgentLevel,
     pub layer: NetworkLayer,
     pub connections_count: usize,
+    /// Moving average score per question category
+    #[serde(default)]
+    pub specialization: Specialization,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -40,6 +62,50 @@ pub struct ConnectionEdge {
     pub strength: f32,
     pub interaction_count: u64,
     pub last_interaction: chrono::DateTime<chrono::Utc>,
+    /// When `strength` last changed, by interaction or decay
+    #[serde(default = "Utc::now")]
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Weakening of connections that have not been updated for a while,
+/// applied when a stored topology is restored
+#[derive(Debug, Clone, Copy)]
+pub struct ConnectionDecay {
+    /// Idle time after which a connection keeps half its strength
+    pub half_life: chrono::Duration,
+    /// Strength decay never goes below
+    pub min_strength: f32,
+}
+
+impl Default for ConnectionDecay {
+    fn default() -> Self {
+        Self {
+            half_life: chrono::Duration::days(7),
+            min_strength: 0.1,
+        }
+    }
+}
+
+impl ConnectionDecay {
+    /// Decay `edge` for the time since it was last updated. Returns whether
+    /// its strength changed.
+    pub fn apply(&self, edge: &mut ConnectionEdge, now: DateTime<Utc>) -> bool {
+        let age = (now - edge.updated_at).num_milliseconds();
+        let half_life = self.half_life.num_milliseconds();
+        if age <= 0 || half_life <= 0 {
+            return false;
+        }
+        
+        let factor = 0.5f64.powf(age as f64 / half_life as f64);
+        let decayed = ((edge.strength as f64 * factor) as f32).max(self.min_strength);
+        if decayed < edge.strength {
+            edge.strength = decayed;
+            edge.updated_at = now;
+            true
+        } else {
+            false
+        }
+    }
 }
 
 impl Default for NetworkTopology {
@@ -51,9 +117,91 @@ impl Default for NetworkTopology {
 impl NetworkTopology {
     pub fn new() -> Self {
         Self {
-            graph: Arc::new(RwLock::new(DiGraph::new())),
-            agent_indices: Arc::new(DashMap::new()),
+            graph: Arc::new(RwLock::new(AgentGraph::default())),
             layer_groups: Arc::new(DashMap::new()),
+            store: None,
+        }
+    }
+    
+    /// Build a topology from a snapshot, without a store
+    pub fn from_snapshot(snapshot: TopologySnapshot) -> Self {
+        let topology = Self::new();
+        let mut graph = AgentGraph::default();
+        
+        for node in snapshot.agents {
+            let id = node.id;
+            let layer = node.layer;
+            if graph.add_node(node) {
+                topology.layer_groups.entry(layer).or_default().push(id);
+            }
+        }
+        // Connections of agents not in the snapshot are dropped
+        for connection in snapshot.connections {
+            graph.add_edge(connection.from, connection.to, connection.edge);
+        }
+        
+        Self {
+            graph: Arc::new(RwLock::new(graph)),
+            ..topology
+        }
+    }
+    
+    /// Rebuild the topology kept in `store`, decaying connections by the
+    /// time they sat idle, and record further changes to it. The store is
+    /// compacted to the restored state.
+    pub async fn restore(store: Arc<dyn TopologyStore>, decay: Option<ConnectionDecay>) -> AgentResult<Self> {
+        let mut snapshot = store.load()?.unwrap_or_default();
+        let now = Utc::now();
+        if let Some(decay) = decay {
+            for connection in &mut snapshot.connections {
+                decay.apply(&mut connection.edge, now);
+            }
+        }
+        snapshot.saved_at = Some(now);
+        store.compact(&snapshot)?;
+        
+        let mut topology = Self::from_snapshot(snapshot);
+        topology.store = Some(store);
+        Ok(topology)
+    }
+    
+    /// Current agents and connections in graph order
+    pub async fn snapshot(&self) -> TopologySnapshot {
+        let graph = self.graph.read().await;
+        Self::snapshot_of(&graph)
+    }
+    
+    fn snapshot_of(graph: &AgentGraph) -> TopologySnapshot {
+        TopologySnapshot {
+            agents: graph.nodes().cloned().collect(),
+            connections: graph.edges()
+                .map(|edge| PersistedConnection {
+                    from: edge.from,
+                    to: edge.to,
+                    edge: edge.weight.clone(),
+                })
+                .collect(),
+            saved_at: Some(Utc::now()),
+        }
+    }
+    
+    /// Fold the store's journal into a snapshot of the current state
+    pub async fn compact(&self) -> AgentResult<()> {
+        if let Some(store) = &self.store {
+            // The read lock holds off changes until the snapshot is stored
+            let graph = self.graph.read().await;
+            store.compact(&Self::snapshot_of(&graph))?;
+        }
+        Ok(())
+    }
+    
+    /// Record a change in the store. Callers hold the graph write lock so
+    /// the journal sees changes in the order they were applied.
+    fn record(&self, change: TopologyChange) {
+        if let Some(store) = &self.store {
+            if let Err(e) = store.record(&change) {
+                tracing::warn!("Failed to persist topology change: {}", e);
+            }
         }
     }
     
@@ -69,21 +217,22 @@ impl NetworkTopology {
             level: profile.capability_level,
             layer,
             connections_count: 0,
+            specialization: profile.specialization.clone(),
         };
         
         // Add to graph
         let mut graph = self.graph.write().await;
-        let node_idx = graph.add_node(node.clone());
+        let placed = graph.add_node(node.clone());
+        self.record(TopologyChange::AgentPlaced { node });
         drop(graph);
         
-        // Update indices
-        self.agent_indices.insert(profile.id, node_idx);
-        
         // Add to layer group
-        self.layer_groups
-            .entry(layer)
-            .or_default()
-            .push(profile.id);
+        if placed {
+            self.layer_groups
+                .entry(layer)
+                .or_default()
+                .push(profile.id);
+        }
         
         // Find optimal connections
         let connections = self.find_optimal_connections(profile).await;
@@ -102,7 +251,9 @@ impl NetworkTopology {
         }
     }
     
-    /// Find optimal connections for a new agent
+    /// Find optimal connections for a new agent: the first peers and
+    /// mentors placed in its layer and the adjacent ones, so only the heads
+    /// of the layer groups are read
     async fn find_optimal_connections(&self, profile: &AgentProfile) -> Vec<Uuid> {
         let mut connections = Vec::new();
         let target_layer = profile.capability_level.layer();
@@ -129,16 +280,14 @@ impl NetworkTopology {
         
         // Ensure minimum connectivity
         if connections.len() < 2 {
-            // Find any available agents
+            // Find any available agents, stopping at the third
             let graph = self.graph.read().await;
-            for node_idx in graph.node_indices() {
+            for node in graph.nodes() {
                 if connections.len() >= 3 {
                     break;
                 }
-                if let Some(node) = graph.node_weight(node_idx) {
-                    if node.id != profile.id && !connections.contains(&node.id) {
-                        connections.push(node.id);
-                    }
+                if node.id != profile.id && !connections.contains(&node.id) {
+                    connections.push(node.id);
                 }
             }
         }
@@ -148,26 +297,26 @@ impl NetworkTopology {
     
     /// Connect two agents
     pub async fn connect_agents(&self, agent1: Uuid, agent2: Uuid, initial_strength: f32) {
-        let idx1 = self.agent_indices.get(&agent1).map(|v| *v);
-        let idx2 = self.agent_indices.get(&agent2).map(|v| *v);
+        let mut graph = self.graph.write().await;
         
-        if let (Some(idx1), Some(idx2)) = (idx1, idx2) {
-            let mut graph = self.graph.write().await;
-            
+        if graph.contains(&agent1) && graph.contains(&agent2) {
+            let now = chrono::Utc::now();
             let edge = ConnectionEdge {
                 strength: initial_strength,
                 interaction_count: 0,
-                last_interaction: chrono::Utc::now(),
+                last_interaction: now,
+                updated_at: now,
             };
             
-            graph.add_edge(idx1, idx2, edge.clone());
-            graph.add_edge(idx2, idx1, edge); // Bidirectional
+            graph.add_edge(agent1, agent2, edge.clone());
+            graph.add_edge(agent2, agent1, edge.clone()); // Bidirectional
+            self.record(TopologyChange::Connected { agent1, agent2, edge });
             
             // Update connection counts
-            if let Some(node1) = graph.node_weight_mut(idx1) {
+            if let Some(node1) = graph.node_mut(&agent1) {
                 node1.connections_count += 1;
             }
-            if let Some(node2) = graph.node_weight_mut(idx2) {
+            if let Some(node2) = graph.node_mut(&agent2) {
                 node2.connections_count += 1;
             }
         }
@@ -175,59 +324,151 @@ impl NetworkTopology {
     
     /// Check if two agents are connected
     pub async fn are_connected(&self, agent1: Uuid, agent2: Uuid) -> bool {
-        let idx1 = self.agent_indices.get(&agent1).map(|v| *v);
-        let idx2 = self.agent_indices.get(&agent2).map(|v| *v);
-        
-        if let (Some(idx1), Some(idx2)) = (idx1, idx2) {
-            let graph = self.graph.read().await;
-            graph.find_edge(idx1, idx2).is_some() || graph.find_edge(idx2, idx1).is_some()
-        } else {
-            false
-        }
+        let graph = self.graph.read().await;
+        graph.has_edge(&agent1, &agent2) || graph.has_edge(&agent2, &agent1)
     }
     
     /// Update connection strength based on interaction
     pub async fn update_connection(&self, agent1: Uuid, agent2: Uuid, success: bool) {
-        let idx1 = self.agent_indices.get(&agent1).map(|v| *v);
-        let idx2 = self.agent_indices.get(&agent2).map(|v| *v);
+        let mut graph = self.graph.write().await;
         
-        if let (Some(idx1), Some(idx2)) = (idx1, idx2) {
-            let mut graph = self.graph.write().await;
+        // Update the latest edge from agent1 to agent2
+        if let Some(edge) = graph.find_edge_mut(&agent1, &agent2) {
+            edge.interaction_count += 1;
+            edge.last_interaction = chrono::Utc::now();
+            edge.updated_at = edge.last_interaction;
+            
+            // Adjust strength based on success
+            if success {
+                edge.strength = (edge.strength * 1.1).min(1.0);
+            } else {
+                edge.strength = (edge.strength * 0.9).max(0.1);
+            }
             
-            // Update edge from agent1 to agent2
-            if let Some(edge_idx) = graph.find_edge(idx1, idx2) {
-                if let Some(edge) = graph.edge_weight_mut(edge_idx) {
-                    edge.interaction_count += 1;
-                    edge.last_interaction = chrono::Utc::now();
-                    
-                    // Adjust strength based on success
-                    if success {
-                        edge.strength = (edge.strength * 1.1).min(1.0);
-                    } else {
-                        edge.strength = (edge.strength * 0.9).max(0.1);
-                    }
+            let edge = edge.clone();
+            self.record(TopologyChange::ConnectionUpdated { from: agent1, to: agent2, edge });
+        }
+    }
+    
+    /// Give a placed agent a new level, moving it to the level's layer.
+    /// Its connections are kept. Returns false for unknown agents.
+    pub async fn move_agent(&self, agent_id: Uuid, level: AgentLevel) -> bool {
+        let mut graph = self.graph.write().await;
+        let node = match graph.node_mut(&agent_id) {
+            Some(node) => node,
+            None => return false,
+        };
+        let old_layer = node.layer;
+        node.level = level;
+        node.layer = level.layer();
+        self.record(TopologyChange::AgentMoved { id: agent_id, level });
+        drop(graph);
+        
+        if old_layer != level.layer() {
+            if let Some(mut agents) = self.layer_groups.get_mut(&old_layer) {
+                agents.retain(|id| id != &agent_id);
+            }
+            self.layer_groups.remove_if(&old_layer, |_, agents| agents.is_empty());
+            self.layer_groups.entry(level.layer()).or_default().push(agent_id);
+        }
+        true
+    }
+    
+    /// Fold an evaluation's category scores into the agent's
+    /// specialization. Returns the updated specialization, `None` for
+    /// unknown agents.
+    pub async fn record_evaluation(&self, agent_id: Uuid, result: &EvaluationResult) -> Option<Specialization> {
+        let scores = result.category_scores.iter().map(|(category, score)| (*category, *score));
+        self.observe_specialization(agent_id, scores).await
+    }
+    
+    /// Fold a peer review into the reviewed agent's specialization. Reviews
+    /// not of a question in some category are ignored.
+    pub async fn record_peer_review(&self, review: &MutualEvaluation) -> Option<Specialization> {
+        let category = review.category?;
+        self.observe_specialization(review.evaluated, [(category, review.scores.overall())]).await
+    }
+    
+    async fn observe_specialization(
+        &self,
+        agent_id: Uuid,
+        scores: impl IntoIterator<Item = (QuestionCategory, f32)>,
+    ) -> Option<Specialization> {
+        let mut graph = self.graph.write().await;
+        let node = graph.node_mut(&agent_id)?;
+        for (category, score) in scores {
+            node.specialization.observe(category, score, specialization::SMOOTHING);
+        }
+        let specialization = node.specialization.clone();
+        self.record(TopologyChange::SpecializationUpdated { id: agent_id, specialization: specialization.clone() });
+        Some(specialization)
+    }
+    
+    /// Current specialization of a placed agent
+    pub async fn specialization(&self, agent_id: Uuid) -> Option<Specialization> {
+        let graph = self.graph.read().await;
+        graph.node(&agent_id).map(|node| node.specialization.clone())
+    }
+    
+    /// The `top_k` agents best fit for `category`: their average score in
+    /// it weighted by their layer. Agents never scored in the category are
+    /// left out.
+    pub async fn find_specialists(&self, category: QuestionCategory, top_k: usize) -> Vec<Specialist> {
+        let graph = self.graph.read().await;
+        let mut specialists: Vec<Specialist> = graph.nodes()
+            .filter_map(|node| {
+                let score = node.specialization.score(category)?;
+                Some(Specialist {
+                    agent_id: node.id,
+                    level: node.level,
+                    layer: node.layer,
+                    score,
+                    fit: score * specialization::layer_weight(node.layer),
+                })
+            })
+            .collect();
+        
+        specialists.sort_by(|a, b| {
+            b.fit.total_cmp(&a.fit)
+                .then(b.score.total_cmp(&a.score))
+                .then(a.agent_id.cmp(&b.agent_id))
+        });
+        specialists.truncate(top_k);
+        specialists
+    }
+    
+    /// Agents that are the only one averaging at least `threshold` in some
+    /// category
+    pub async fn sole_specialists(&self, threshold: f32) -> HashSet<Uuid> {
+        let graph = self.graph.read().await;
+        let mut strong: HashMap<QuestionCategory, Vec<Uuid>> = HashMap::new();
+        for node in graph.nodes() {
+            for (category, skill) in node.specialization.iter() {
+                if skill.score >= threshold {
+                    strong.entry(category).or_default().push(node.id);
                 }
             }
         }
+        
+        strong.into_values()
+            .filter(|agents| agents.len() == 1)
+            .map(|agents| agents[0])
+            .collect()
     }
     
     /// Remove an agent from the network
     pub async fn remove_agent(&self, agent_id: Uuid) {
-        if let Some((_, node_idx)) = self.agent_indices.remove(&agent_id) {
-            let mut graph = self.graph.write().await;
-            
-            // Get the agent's layer before removal
-            let layer = graph.node_weight(node_idx).map(|n| n.layer);
-            
-            // Remove from graph
-            graph.remove_node(node_idx);
+        let mut graph = self.graph.write().await;
+        
+        // Remove from graph along with its connections
+        if let Some(node) = graph.remove_node(&agent_id) {
+            self.record(TopologyChange::AgentRemoved { id: agent_id });
             
             // Remove from layer groups
-            if let Some(layer) = layer {
-                if let Some(mut agents) = self.layer_groups.get_mut(&layer) {
-                    agents.retain(|id| id != &agent_id);
-                }
+            if let Some(mut agents) = self.layer_groups.get_mut(&node.layer) {
+                agents.retain(|id| id != &agent_id);
             }
+            self.layer_groups.remove_if(&node.layer, |_, agents| agents.is_empty());
         }
     }
     
@@ -247,11 +488,9 @@ impl NetworkTopology {
         let mut avg_level = 0.0;
         
         for agent_id in &layer_agents {
-            if let Some(node_idx) = self.agent_indices.get(agent_id) {
-                if let Some(node) = graph.node_weight(*node_idx) {
-                    total_connections += node.connections_count;
-                    avg_level += node.level.value() as f32;
-                }
+            if let Some(node) = graph.node(agent_id) {
+                total_connections += node.connections_count;
+                avg_level += node.level.value() as f32;
             }
         }
         
@@ -273,7 +512,8 @@ impl NetworkTopology {
         }
     }
     
-    /// Get overall network statistics
+    /// Get overall network statistics. Degrees and triangles are kept up
+    /// to date as connections change, so this is one pass over the agents.
     pub async fn get_network_stats(&self) -> NetworkStats {
         let graph = self.graph.read().await;
         let total_agents = graph.node_count();
@@ -290,11 +530,41 @@ impl NetworkTopology {
             0.0
         };
         
+        // Structure of the undirected graph, ignoring parallel edges
+        let degree_distribution = graph.degree_distribution().clone();
+        let clustering_sum = graph.clustering_sum();
+        
+        let mut category_scores: HashMap<QuestionCategory, Vec<f32>> = HashMap::new();
+        for node in graph.nodes() {
+            for (category, skill) in node.specialization.iter() {
+                category_scores.entry(category).or_default().push(skill.score);
+            }
+        }
+        let specialization_distribution = category_scores
+            .into_iter()
+            .map(|(category, scores)| {
+                let distribution = CategoryDistribution {
+                    agents: scores.len(),
+                    specialists: scores.iter().filter(|&&score| score >= specialization::SPECIALIST_SCORE).count(),
+                    mean_score: scores.iter().sum::<f32>() / scores.len() as f32,
+                    top_score: scores.iter().copied().fold(0.0, f32::max),
+                };
+                (category, distribution)
+            })
+            .collect();
+        
         NetworkStats {
             total_agents,
             total_connections,
             average_connectivity,
             layer_distribution,
+            degree_distribution,
+            clustering_coefficient: if total_agents > 0 {
+                (clustering_sum / total_agents as f64) as f32
+            } else {
+                0.0
+            },
+            specialization_distribution,
         }
     }
     
@@ -302,8 +572,8 @@ impl NetworkTopology {
     async fn calculate_position_quality(&self, profile: &AgentProfile) -> f32 {
         let graph = self.graph.read().await;
         
-        if let Some(node_idx) = self.agent_indices.get(&profile.id) {
-            let connections = graph.edges(*node_idx).count();
+        if graph.contains(&profile.id) {
+            let connections = graph.out_degree(&profile.id);
             let optimal_connections = match profile.capability_level.layer() {
                 NetworkLayer::Basic => 2.0,
                 NetworkLayer::Intermediate => 4.0,
@@ -315,8 +585,8 @@ impl NetworkTopology {
             
             // Factor in connection strengths
             let mut strength_sum = 0.0;
-            for edge in graph.edges(*node_idx) {
-                strength_sum += edge.weight().strength;
+            for edge in graph.outgoing(&profile.id) {
+                strength_sum += edge.strength;
             }
             let avg_strength = if connections > 0 {
                 strength_sum / connections as f32
@@ -347,33 +617,23 @@ impl NetworkTopology {
         let mut edges = Vec::new();
         
         // Export nodes
-        for node_idx in graph.node_indices() {
-            if let Some(node) = graph.node_weight(node_idx) {
-                nodes.push(VisualizationNode {
-                    id: node.id.to_string(),
-                    label: format!("L{}", node.level.value()),
-                    layer: node.layer,
-                    size: node.connections_count as f32,
-                });
-            }
+        for node in graph.nodes() {
+            nodes.push(VisualizationNode {
+                id: node.id.to_string(),
+                label: format!("L{}", node.level.value()),
+                layer: node.layer,
+                size: node.connections_count as f32,
+            });
         }
         
         // Export edges
-        for edge_ref in graph.edge_references() {
-            let source_idx = edge_ref.source();
-            let target_idx = edge_ref.target();
-            
-            if let (Some(source), Some(target)) = (
-                graph.node_weight(source_idx),
-                graph.node_weight(target_idx)
-            ) {
-                edges.push(VisualizationEdge {
-                    source: source.id.to_string(),
-                    target: target.id.to_string(),
-                    weight: edge_ref.weight().strength,
-                    interactions: edge_ref.weight().interaction_count,
-                });
-            }
+        for edge in graph.edges() {
+            edges.push(VisualizationEdge {
+                source: edge.from.to_string(),
+                target: edge.to.to_string(),
+                weight: edge.weight.strength,
+                interactions: edge.weight.interaction_count,
+            });
         }
         
         NetworkVisualization {
@@ -382,6 +642,71 @@ impl NetworkTopology {
             timestamp: chrono::Utc::now(),
         }
     }
+    
+    /// Export the topology as GraphML, e.g. for Gephi
+    pub async fn export_graphml(&self) -> String {
+        let graph = self.graph.read().await;
+        let mut out = String::new();
+        
+        out.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        out.push_str("<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n");
+        out.push_str("  <key id=\"level\" for=\"node\" attr.name=\"level\" attr.type=\"int\"/>\n");
+        out.push_str("  <key id=\"layer\" for=\"node\" attr.name=\"layer\" attr.type=\"string\"/>\n");
+        out.push_str("  <key id=\"connections\" for=\"node\" attr.name=\"connections\" attr.type=\"int\"/>\n");
+        out.push_str("  <key id=\"weight\" for=\"edge\" attr.name=\"weight\" attr.type=\"double\"/>\n");
+        out.push_str("  <key id=\"interactions\" for=\"edge\" attr.name=\"interactions\" attr.type=\"long\"/>\n");
+        out.push_str("  <key id=\"updated_at\" for=\"edge\" attr.name=\"updated_at\" attr.type=\"string\"/>\n");
+        out.push_str("  <graph id=\"agents\" edgedefault=\"directed\">\n");
+        
+        for node in graph.nodes() {
+            let _ = writeln!(out, "    <node id=\"{}\">", node.id);
+            let _ = writeln!(out, "      <data key=\"level\">{}</data>", node.level.value());
+            let _ = writeln!(out, "      <data key=\"layer\">{:?}</data>", node.layer);
+            let _ = writeln!(out, "      <data key=\"connections\">{}</data>", node.connections_count);
+            out.push_str("    </node>\n");
+        }
+        
+        for (i, edge) in graph.edges().enumerate() {
+            let weight = &edge.weight;
+            let _ = writeln!(
+                out,
+                "    <edge id=\"e{}\" source=\"{}\" target=\"{}\">",
+                i, edge.from, edge.to
+            );
+            let _ = writeln!(out, "      <data key=\"weight\">{}</data>", weight.strength);
+            let _ = writeln!(out, "      <data key=\"interactions\">{}</data>", weight.interaction_count);
+            let _ = writeln!(out, "      <data key=\"updated_at\">{}</data>", weight.updated_at.to_rfc3339());
+            out.push_str("    </edge>\n");
+        }
+        
+        out.push_str("  </graph>\n</graphml>\n");
+        out
+    }
+    
+    /// Export the topology in Graphviz DOT format
+    pub async fn export_dot(&self) -> String {
+        let graph = self.graph.read().await;
+        let mut out = String::from("digraph agents {\n");
+        
+        for node in graph.nodes() {
+            let _ = writeln!(
+                out,
+                "  \"{}\" [label=\"L{}\", layer=\"{:?}\", connections={}];",
+                node.id, node.level.value(), node.layer, node.connections_count
+            );
+        }
+        for edge in graph.edges() {
+            let _ = writeln!(
+                out,
+                "  \"{}\" -> \"{}\" [weight={}, interactions={}];",
+                edge.from, edge.to,
+                edge.weight.strength, edge.weight.interaction_count
+            );
+        }
+        
+        out.push_str("}\n");
+        out
+    }
 }
 
 /// Network position information
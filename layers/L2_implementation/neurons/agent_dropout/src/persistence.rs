@@ -0,0 +1,200 @@
+//! Topology persistence
+//!
+//! Changes are appended to a journal as they happen; `compact` folds the
+//! journal into a snapshot. Loading reads the snapshot and replays the
+//! journal on top of it.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use uuid::Uuid;
+
+use crate::agent::AgentLevel;
+use crate::network::{AgentNode, ConnectionEdge};
+use crate::specialization::Specialization;
+use crate::{AgentError, AgentResult};
+
+const SNAPSHOT_FILE: &str = "topology.json";
+const JOURNAL_FILE: &str = "topology.journal";
+
+/// A single change to the topology
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "change", rename_all = "snake_case")]
+pub enum TopologyChange {
+    AgentPlaced { node: AgentNode },
+    AgentRemoved { id: Uuid },
+    /// New level, and with it the layer, of a placed agent
+    AgentMoved { id: Uuid, level: AgentLevel },
+    /// Both directions of a new connection
+    Connected { agent1: Uuid, agent2: Uuid, edge: ConnectionEdge },
+    /// New weight of the first `from` -> `to` edge
+    ConnectionUpdated { from: Uuid, to: Uuid, edge: ConnectionEdge },
+    /// Category averages of a placed agent after an evaluation
+    SpecializationUpdated { id: Uuid, specialization: Specialization },
+}
+
+/// A directed, weighted connection
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PersistedConnection {
+    pub from: Uuid,
+    pub to: Uuid,
+    pub edge: ConnectionEdge,
+}
+
+/// Full topology state, agents and connections in graph order
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TopologySnapshot {
+    pub agents: Vec<AgentNode>,
+    pub connections: Vec<PersistedConnection>,
+    pub saved_at: Option<DateTime<Utc>>,
+}
+
+impl TopologySnapshot {
+    /// Apply a journaled change. Changes referring to unknown agents are
+    /// ignored.
+    pub fn apply(&mut self, change: TopologyChange) {
+        match change {
+            TopologyChange::AgentPlaced { node } => {
+                self.agents.retain(|a| a.id != node.id);
+                self.agents.push(node);
+            }
+            TopologyChange::AgentRemoved { id } => {
+                // Mirror the graph, which moves its last node into the gap
+                if let Some(index) = self.agents.iter().position(|a| a.id == id) {
+                    self.agents.swap_remove(index);
+                }
+                self.connections.retain(|c| c.from != id && c.to != id);
+            }
+            TopologyChange::AgentMoved { id, level } => {
+                if let Some(agent) = self.agents.iter_mut().find(|a| a.id == id) {
+                    agent.level = level;
+                    agent.layer = level.layer();
+                }
+            }
+            TopologyChange::Connected { agent1, agent2, edge } => {
+                if !self.has_agent(agent1) || !self.has_agent(agent2) {
+                    return;
+                }
+                for agent in self.agents.iter_mut().filter(|a| a.id == agent1 || a.id == agent2) {
+                    agent.connections_count += 1;
+                }
+                self.connections.push(PersistedConnection { from: agent1, to: agent2, edge: edge.clone() });
+                self.connections.push(PersistedConnection { from: agent2, to: agent1, edge });
+            }
+            TopologyChange::ConnectionUpdated { from, to, edge } => {
+                if let Some(connection) = self.connections.iter_mut().find(|c| c.from == from && c.to == to) {
+                    connection.edge = edge;
+                }
+            }
+            TopologyChange::SpecializationUpdated { id, specialization } => {
+                if let Some(agent) = self.agents.iter_mut().find(|a| a.id == id) {
+                    agent.specialization = specialization;
+                }
+            }
+        }
+    }
+
+    fn has_agent(&self, id: Uuid) -> bool {
+        self.agents.iter().any(|a| a.id == id)
+    }
+}
+
+/// Storage for a topology's changes
+pub trait TopologyStore: Send + Sync {
+    /// Persist one change
+    fn record(&self, change: &TopologyChange) -> AgentResult<()>;
+
+    /// Current state, `None` if nothing was ever stored
+    fn load(&self) -> AgentResult<Option<TopologySnapshot>>;
+
+    /// Replace the stored state with `snapshot`
+    fn compact(&self, snapshot: &TopologySnapshot) -> AgentResult<()>;
+}
+
+/// Store keeping a JSON snapshot and a JSON-lines journal in a directory
+pub struct FileTopologyStore {
+    dir: PathBuf,
+    journal: Mutex<File>,
+}
+
+pub(crate) fn persistence_error(context: &str, e: impl std::fmt::Display) -> AgentError {
+    AgentError::Persistence(format!("{}: {}", context, e))
+}
+
+impl FileTopologyStore {
+    /// Open or create a store in `dir`
+    pub fn open(dir: impl AsRef<Path>) -> AgentResult<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir).map_err(|e| persistence_error("create store directory", e))?;
+        let journal = Self::open_journal(&dir, false)?;
+        Ok(Self { dir, journal: Mutex::new(journal) })
+    }
+
+    fn open_journal(dir: &Path, truncate: bool) -> AgentResult<File> {
+        let mut options = OpenOptions::new();
+        options.create(true);
+        if truncate {
+            options.write(true).truncate(true);
+        } else {
+            options.append(true);
+        }
+        options.open(dir.join(JOURNAL_FILE)).map_err(|e| persistence_error("open journal", e))
+    }
+}
+
+impl TopologyStore for FileTopologyStore {
+    fn record(&self, change: &TopologyChange) -> AgentResult<()> {
+        let mut line = serde_json::to_vec(change).map_err(|e| persistence_error("encode change", e))?;
+        line.push(b'\n');
+        let mut journal = self.journal.lock().unwrap();
+        journal.write_all(&line).map_err(|e| persistence_error("append to journal", e))
+    }
+
+    fn load(&self) -> AgentResult<Option<TopologySnapshot>> {
+        let snapshot_path = self.dir.join(SNAPSHOT_FILE);
+        let journal_path = self.dir.join(JOURNAL_FILE);
+        let journal_empty = fs::metadata(&journal_path).map(|m| m.len() == 0).unwrap_or(true);
+        if !snapshot_path.exists() && journal_empty {
+            return Ok(None);
+        }
+
+        let mut snapshot = match fs::read(&snapshot_path) {
+            Ok(bytes) => serde_json::from_slice(&bytes).map_err(|e| persistence_error("decode snapshot", e))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => TopologySnapshot::default(),
+            Err(e) => return Err(persistence_error("read snapshot", e)),
+        };
+
+        let journal = File::open(&journal_path).map_err(|e| persistence_error("read journal", e))?;
+        for line in BufReader::new(journal).lines() {
+            let line = line.map_err(|e| persistence_error("read journal", e))?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str(&line) {
+                Ok(change) => snapshot.apply(change),
+                // A crash mid-append leaves a torn last line; everything
+                // before it is intact
+                Err(e) => {
+                    tracing::warn!("Stopping journal replay at unreadable entry: {}", e);
+                    break;
+                }
+            }
+        }
+        Ok(Some(snapshot))
+    }
+
+    fn compact(&self, snapshot: &TopologySnapshot) -> AgentResult<()> {
+        let bytes = serde_json::to_vec(snapshot).map_err(|e| persistence_error("encode snapshot", e))?;
+        let mut journal = self.journal.lock().unwrap();
+
+        let tmp = self.dir.join(format!("{}.tmp", SNAPSHOT_FILE));
+        fs::write(&tmp, bytes).map_err(|e| persistence_error("write snapshot", e))?;
+        fs::rename(&tmp, self.dir.join(SNAPSHOT_FILE)).map_err(|e| persistence_error("write snapshot", e))?;
+
+        *journal = Self::open_journal(&self.dir, true)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,346 @@
+//! Periodic re-assessment of placed agents
+//!
+//! Every agent is due for re-assessment a while after its last one, sooner
+//! in lower layers. A due agent answers a short question set spread over the
+//! pool's difficulty. When the estimated level is further from its current
+//! level than the hysteresis band, the agent takes the estimate and moves to
+//! its layer; agents that just moved are left alone for a cool-down.
+//! With a dropout orchestrator attached, every re-assessment also counts
+//! as one of the evaluations its dropout decisions rest on.
+
+use chrono::{DateTime, Duration, Utc};
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::agent::{AgentEntry, AgentLevel, NetworkLayer};
+use crate::assessment::AssessmentPool;
+use crate::dropout::DropoutOrchestrator;
+use crate::evaluation::EvaluationEngine;
+use crate::network::NetworkTopology;
+use crate::persistence::persistence_error;
+use crate::{AgentError, AgentResult};
+
+const SCHEDULE_FILE: &str = "schedule.json";
+
+/// When and how agents are re-assessed
+#[derive(Debug, Clone)]
+pub struct ScheduleConfig {
+    pub basic_interval: Duration,
+    pub intermediate_interval: Duration,
+    pub advanced_interval: Duration,
+    /// Levels an estimate may differ from the current level without a move
+    pub hysteresis: u8,
+    /// Time after a move during which an agent is not re-assessed
+    pub cooldown: Duration,
+    /// Questions in a re-assessment
+    pub questions: usize,
+}
+
+impl Default for ScheduleConfig {
+    fn default() -> Self {
+        Self {
+            basic_interval: Duration::days(1),
+            intermediate_interval: Duration::days(3),
+            advanced_interval: Duration::days(7),
+            hysteresis: 2,
+            cooldown: Duration::days(3),
+            questions: 10,
+        }
+    }
+}
+
+impl ScheduleConfig {
+    /// Time between re-assessments of agents in `layer`
+    pub fn interval(&self, layer: NetworkLayer) -> Duration {
+        match layer {
+            NetworkLayer::Basic => self.basic_interval,
+            NetworkLayer::Intermediate => self.intermediate_interval,
+            NetworkLayer::Advanced => self.advanced_interval,
+        }
+    }
+}
+
+/// An agent's place in the schedule
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScheduleEntry {
+    pub agent_id: Uuid,
+    pub level: AgentLevel,
+    pub due_at: DateTime<Utc>,
+    pub last_assessed: Option<DateTime<Utc>>,
+    pub moved_at: Option<DateTime<Utc>>,
+}
+
+/// Full scheduler state, agents ordered by due time
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ScheduleState {
+    pub agents: Vec<ScheduleEntry>,
+    pub saved_at: Option<DateTime<Utc>>,
+}
+
+/// Storage for the scheduler's state
+pub trait ScheduleStore: Send + Sync {
+    /// Stored state, `None` if nothing was ever stored
+    fn load(&self) -> AgentResult<Option<ScheduleState>>;
+
+    /// Replace the stored state with `state`
+    fn save(&self, state: &ScheduleState) -> AgentResult<()>;
+}
+
+/// Store keeping the state as a JSON file in a directory
+pub struct FileScheduleStore {
+    dir: PathBuf,
+}
+
+impl FileScheduleStore {
+    /// Open or create a store in `dir`
+    pub fn open(dir: impl AsRef<Path>) -> AgentResult<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir).map_err(|e| persistence_error("create store directory", e))?;
+        Ok(Self { dir })
+    }
+}
+
+impl ScheduleStore for FileScheduleStore {
+    fn load(&self) -> AgentResult<Option<ScheduleState>> {
+        match fs::read(self.dir.join(SCHEDULE_FILE)) {
+            Ok(bytes) => serde_json::from_slice(&bytes)
+                .map(Some)
+                .map_err(|e| persistence_error("decode schedule", e)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(persistence_error("read schedule", e)),
+        }
+    }
+
+    fn save(&self, state: &ScheduleState) -> AgentResult<()> {
+        let bytes = serde_json::to_vec(state).map_err(|e| persistence_error("encode schedule", e))?;
+        let tmp = self.dir.join(format!("{}.tmp", SCHEDULE_FILE));
+        fs::write(&tmp, bytes).map_err(|e| persistence_error("write schedule", e))?;
+        fs::rename(&tmp, self.dir.join(SCHEDULE_FILE)).map_err(|e| persistence_error("write schedule", e))
+    }
+}
+
+/// Outcome of one re-assessment
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Reassessment {
+    pub agent_id: Uuid,
+    pub previous_level: AgentLevel,
+    pub estimate: AgentLevel,
+    /// Level after the re-assessment: the estimate if the agent moved
+    pub level: AgentLevel,
+    pub moved: bool,
+    pub overall_score: f32,
+    pub next_due: DateTime<Utc>,
+}
+
+/// Scheduler re-assessing placed agents
+pub struct AssessmentScheduler {
+    config: ScheduleConfig,
+    pool: Arc<AssessmentPool>,
+    engine: Arc<EvaluationEngine>,
+    topology: Arc<NetworkTopology>,
+    entries: DashMap<Uuid, ScheduleEntry>,
+    agents: DashMap<Uuid, Arc<dyn AgentEntry>>,
+    store: Option<Arc<dyn ScheduleStore>>,
+    dropout: Option<Arc<DropoutOrchestrator>>,
+}
+
+impl AssessmentScheduler {
+    pub fn new(
+        config: ScheduleConfig,
+        pool: Arc<AssessmentPool>,
+        engine: Arc<EvaluationEngine>,
+        topology: Arc<NetworkTopology>,
+    ) -> Self {
+        Self {
+            config,
+            pool,
+            engine,
+            topology,
+            entries: DashMap::new(),
+            agents: DashMap::new(),
+            store: None,
+            dropout: None,
+        }
+    }
+
+    /// Record each re-assessment's overall score with `dropout`
+    pub fn with_dropout(mut self, dropout: Arc<DropoutOrchestrator>) -> Self {
+        self.dropout = Some(dropout);
+        self
+    }
+
+    /// Scheduler picking up the due times kept in `store`, and saving
+    /// further changes to it
+    pub fn restore(
+        store: Arc<dyn ScheduleStore>,
+        config: ScheduleConfig,
+        pool: Arc<AssessmentPool>,
+        engine: Arc<EvaluationEngine>,
+        topology: Arc<NetworkTopology>,
+    ) -> AgentResult<Self> {
+        let state = store.load()?.unwrap_or_default();
+        let mut scheduler = Self::new(config, pool, engine, topology);
+        for entry in state.agents {
+            scheduler.entries.insert(entry.agent_id, entry);
+        }
+        scheduler.store = Some(store);
+        Ok(scheduler)
+    }
+
+    /// Current schedule, soonest due first
+    pub fn state(&self) -> ScheduleState {
+        let mut agents: Vec<ScheduleEntry> = self.entries.iter().map(|entry| entry.clone()).collect();
+        agents.sort_by_key(|entry| (entry.due_at, entry.agent_id));
+        ScheduleState { agents, saved_at: Some(Utc::now()) }
+    }
+
+    fn save(&self) {
+        if let Some(store) = &self.store {
+            if let Err(e) = store.save(&self.state()) {
+                tracing::warn!("Failed to persist assessment schedule: {}", e);
+            }
+        }
+    }
+
+    /// Schedule a placed agent at `level`, first due one interval from
+    /// `now`. An agent already in the schedule, as after a restart, keeps
+    /// its entry and only gets its handle back.
+    pub fn register(&self, agent: Arc<dyn AgentEntry>, level: AgentLevel, now: DateTime<Utc>) -> ScheduleEntry {
+        let agent_id = agent.introduce().id;
+        self.agents.insert(agent_id, agent);
+
+        let mut created = false;
+        let entry = self
+            .entries
+            .entry(agent_id)
+            .or_insert_with(|| {
+                created = true;
+                ScheduleEntry {
+                    agent_id,
+                    level,
+                    due_at: now + self.config.interval(level.layer()),
+                    last_assessed: None,
+                    moved_at: None,
+                }
+            })
+            .clone();
+        if created {
+            self.save();
+        }
+        entry
+    }
+
+    /// Drop an agent from the schedule, as when it leaves the network
+    pub fn unregister(&self, agent_id: Uuid) {
+        self.agents.remove(&agent_id);
+        if self.entries.remove(&agent_id).is_some() {
+            self.save();
+        }
+    }
+
+    pub fn entry(&self, agent_id: Uuid) -> Option<ScheduleEntry> {
+        self.entries.get(&agent_id).map(|entry| entry.clone())
+    }
+
+    /// Note a level change made elsewhere; the agent starts a cool-down
+    pub fn record_move(&self, agent_id: Uuid, level: AgentLevel, now: DateTime<Utc>) {
+        let Some(mut entry) = self.entries.get_mut(&agent_id) else {
+            return;
+        };
+        entry.level = level;
+        entry.moved_at = Some(now);
+        drop(entry);
+        self.save();
+    }
+
+    fn cooling_down(&self, entry: &ScheduleEntry, now: DateTime<Utc>) -> bool {
+        entry.moved_at.is_some_and(|moved_at| now - moved_at < self.config.cooldown)
+    }
+
+    /// Agents due at `now` and not cooling down, longest overdue first
+    pub fn due(&self, now: DateTime<Utc>) -> Vec<Uuid> {
+        let mut due: Vec<(DateTime<Utc>, Uuid)> = self
+            .entries
+            .iter()
+            .filter(|entry| entry.due_at <= now && !self.cooling_down(entry, now))
+            .map(|entry| (entry.due_at, entry.agent_id))
+            .collect();
+        due.sort();
+        due.into_iter().map(|(_, agent_id)| agent_id).collect()
+    }
+
+    /// Re-assess every due agent. Agents whose handle was not registered
+    /// since a restart wait until it is; a failed re-assessment is logged
+    /// and retried on the next run.
+    pub async fn run_due(&self, now: DateTime<Utc>) -> Vec<Reassessment> {
+        let mut results = Vec::new();
+        for agent_id in self.due(now) {
+            if !self.agents.contains_key(&agent_id) {
+                continue;
+            }
+            match self.reassess(agent_id, now).await {
+                Ok(result) => results.push(result),
+                Err(e) => tracing::warn!("Re-assessment of agent {} failed: {}", agent_id, e),
+            }
+        }
+        results
+    }
+
+    /// Re-assess one agent now, whether due or not
+    pub async fn reassess(&self, agent_id: Uuid, now: DateTime<Utc>) -> AgentResult<Reassessment> {
+        let agent = self.agents.get(&agent_id).map(|agent| agent.clone()).ok_or(AgentError::NotFound(agent_id))?;
+        let previous_level = self.entry(agent_id).ok_or(AgentError::NotFound(agent_id))?.level;
+
+        let mut answers = Vec::with_capacity(self.config.questions);
+        for question in self.pool.get_calibration_set(self.config.questions) {
+            answers.push((question.clone(), agent.answer_assessment(question).await));
+        }
+        let result = self.engine.evaluate(&self.pool, &answers).await?;
+        self.topology.record_evaluation(agent_id, &result).await;
+        if let Some(dropout) = &self.dropout {
+            // Agents the orchestrator does not manage are only re-levelled
+            if let Err(e) = dropout.record_evaluation(agent_id, result.overall_score).await {
+                tracing::debug!("Evaluation of agent {} not recorded for dropout: {}", agent_id, e);
+            }
+        }
+
+        let estimate = result.level_estimate;
+        let moved = estimate.value().abs_diff(previous_level.value()) > self.config.hysteresis;
+        if moved && !self.topology.move_agent(agent_id, estimate).await {
+            return Err(AgentError::NotFound(agent_id));
+        }
+
+        let level = if moved { estimate } else { previous_level };
+        let mut next_due = now + self.config.interval(level.layer());
+        if moved {
+            next_due = next_due.max(now + self.config.cooldown);
+        }
+
+        let mut entry = self.entries.get_mut(&agent_id).ok_or(AgentError::NotFound(agent_id))?;
+        entry.level = level;
+        entry.due_at = next_due;
+        entry.last_assessed = Some(now);
+        if moved {
+            entry.moved_at = Some(now);
+        }
+        drop(entry);
+        self.save();
+
+        if moved {
+            tracing::info!("Agent {} moved from L{} to L{}", agent_id, previous_level.value(), level.value());
+        }
+        Ok(Reassessment {
+            agent_id,
+            previous_level,
+            estimate,
+            level,
+            moved,
+            overall_score: result.overall_score,
+            next_due,
+        })
+    }
+}
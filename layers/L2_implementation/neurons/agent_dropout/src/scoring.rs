@@ -0,0 +1,447 @@
+//! Rubric-based answer scoring
+//!
+//! Every answer is rated on three criteria, combined with per-category
+//! weights:
+//! - correctness: exact match, numeric tolerance, or a judge for free text
+//! - reasoning quality: always judged
+//! - time efficiency: time taken against the question's limit
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::agent::{AssessmentQuestion, AssessmentResponse, QuestionCategory};
+use crate::{AgentError, AgentResult};
+
+/// Time limit assumed for questions without one
+const DEFAULT_TIME_LIMIT: Duration = Duration::from_secs(120);
+
+/// How an answer's correctness is determined
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum AnswerKey {
+    /// Equal to one of `accepted`, ignoring case, whitespace and punctuation
+    ExactMatch { accepted: Vec<String> },
+    /// First number in the answer within `tolerance` of `expected`
+    Numeric { expected: f64, tolerance: f64 },
+    /// Graded by a judge, against `reference` if given
+    FreeText { reference: Option<String> },
+}
+
+impl Default for AnswerKey {
+    fn default() -> Self {
+        AnswerKey::FreeText { reference: None }
+    }
+}
+
+/// Weight of each criterion in an answer's score
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct RubricWeights {
+    pub correctness: f32,
+    pub reasoning: f32,
+    pub time_efficiency: f32,
+}
+
+impl RubricWeights {
+    pub fn new(correctness: f32, reasoning: f32, time_efficiency: f32) -> Self {
+        Self { correctness, reasoning, time_efficiency }
+    }
+
+    /// Weighted score, normalized by the total weight
+    pub fn combine(&self, correctness: f32, reasoning: f32, time_efficiency: f32) -> f32 {
+        let total = self.correctness + self.reasoning + self.time_efficiency;
+        if total <= 0.0 {
+            return 0.0;
+        }
+        (correctness * self.correctness + reasoning * self.reasoning + time_efficiency * self.time_efficiency)
+            / total
+    }
+}
+
+/// Rubric weights per question category
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RubricConfig {
+    /// Weights for categories without their own entry
+    pub default: RubricWeights,
+    pub categories: HashMap<QuestionCategory, RubricWeights>,
+}
+
+impl Default for RubricConfig {
+    fn default() -> Self {
+        let categories = HashMap::from([
+            (QuestionCategory::LogicalReasoning, RubricWeights::new(0.5, 0.35, 0.15)),
+            (QuestionCategory::PatternRecognition, RubricWeights::new(0.6, 0.25, 0.15)),
+            (QuestionCategory::CreativeProblemSolving, RubricWeights::new(0.35, 0.5, 0.15)),
+            (QuestionCategory::SystemsThinking, RubricWeights::new(0.35, 0.5, 0.15)),
+            (QuestionCategory::MetaCognition, RubricWeights::new(0.3, 0.6, 0.1)),
+            (QuestionCategory::EthicalDilemmas, RubricWeights::new(0.3, 0.6, 0.1)),
+        ]);
+        Self {
+            default: RubricWeights::new(0.45, 0.4, 0.15),
+            categories,
+        }
+    }
+}
+
+impl RubricConfig {
+    pub fn weights(&self, category: QuestionCategory) -> RubricWeights {
+        self.categories.get(&category).copied().unwrap_or(self.default)
+    }
+}
+
+/// Scores for one answer, each in 0.0..=1.0
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnswerScore {
+    pub question_id: Uuid,
+    pub category: QuestionCategory,
+    pub difficulty: u8,
+    pub correctness: f32,
+    pub reasoning: f32,
+    pub time_efficiency: f32,
+    /// Criteria combined with the category's rubric weights
+    pub score: f32,
+}
+
+/// A judge's verdict on one answer
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Judgement {
+    pub correctness: f32,
+    pub reasoning: f32,
+}
+
+/// Grades free-text answers and reasoning quality
+#[async_trait]
+pub trait AnswerJudge: Send + Sync {
+    async fn judge(
+        &self,
+        question: &AssessmentQuestion,
+        answer: &str,
+        reference: Option<&str>,
+    ) -> AgentResult<Judgement>;
+}
+
+/// Text completion by a Claude model
+#[async_trait]
+pub trait CompletionClient: Send + Sync {
+    async fn complete(&self, prompt: &str) -> AgentResult<String>;
+}
+
+/// Judge asking a Claude model to grade answers
+pub struct ClaudeJudge<C> {
+    client: C,
+}
+
+impl<C: CompletionClient> ClaudeJudge<C> {
+    pub fn new(client: C) -> Self {
+        Self { client }
+    }
+
+    fn prompt(question: &AssessmentQuestion, answer: &str, reference: Option<&str>) -> String {
+        let mut prompt = format!(
+            "You are grading an answer to an assessment question.\n\n\
+             QUESTION (difficulty L{}, {:?}):\n{}\n\n",
+            question.difficulty.value(),
+            question.category,
+            question.content
+        );
+        if let Some(reference) = reference {
+            prompt.push_str(&format!("REFERENCE ANSWER:\n{}\n\n", reference));
+        }
+        prompt.push_str(&format!(
+            "ANSWER:\n{}\n\n\
+             Rate the answer from 0.0 to 1.0 for correctness and for quality of reasoning. \
+             Reply with exactly these two lines:\nCORRECTNESS: <score>\nREASONING: <score>",
+            answer
+        ));
+        prompt
+    }
+}
+
+/// Parse `CORRECTNESS:` and `REASONING:` lines from a judge's reply
+pub fn parse_judgement(reply: &str) -> AgentResult<Judgement> {
+    let field = |name: &str| {
+        reply
+            .lines()
+            .find_map(|line| line.trim().strip_prefix(name))
+            .and_then(|value| value.trim().parse::<f32>().ok())
+            .map(|value| value.clamp(0.0, 1.0))
+            .ok_or_else(|| AgentError::AssessmentFailed(format!("Judge reply has no {} score", name)))
+    };
+    Ok(Judgement {
+        correctness: field("CORRECTNESS:")?,
+        reasoning: field("REASONING:")?,
+    })
+}
+
+#[async_trait]
+impl<C: CompletionClient> AnswerJudge for ClaudeJudge<C> {
+    async fn judge(
+        &self,
+        question: &AssessmentQuestion,
+        answer: &str,
+        reference: Option<&str>,
+    ) -> AgentResult<Judgement> {
+        let reply = self.client.complete(&Self::prompt(question, answer, reference)).await?;
+        parse_judgement(&reply)
+    }
+}
+
+/// Deterministic judge working from the answer text alone, for tests and
+/// offline evaluation
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HeuristicJudge;
+
+const CONNECTIVES: &[&str] = &[
+    "because", "therefore", "since", "thus", "hence", "so", "if", "then", "implies", "means",
+];
+
+fn words(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// Words long enough to carry meaning
+fn terms(text: &str) -> HashSet<String> {
+    words(text).into_iter().filter(|w| w.chars().count() > 3).collect()
+}
+
+/// Fraction of `expected` words found in `answer`
+fn coverage(expected: &HashSet<String>, answer: &HashSet<String>) -> f32 {
+    if expected.is_empty() {
+        return 0.0;
+    }
+    expected.intersection(answer).count() as f32 / expected.len() as f32
+}
+
+#[async_trait]
+impl AnswerJudge for HeuristicJudge {
+    async fn judge(
+        &self,
+        question: &AssessmentQuestion,
+        answer: &str,
+        reference: Option<&str>,
+    ) -> AgentResult<Judgement> {
+        let answer_words = words(answer);
+        let answer_terms = terms(answer);
+
+        // Explicit inference steps, then sheer development
+        let connectives = answer_words.iter().filter(|w| CONNECTIVES.contains(&w.as_str())).count();
+        let reasoning = 0.5 * connectives.min(4) as f32 / 4.0 + 0.5 * answer_words.len().min(60) as f32 / 60.0;
+
+        let correctness = match reference {
+            // Every word counts against a reference, short ones like "A" included
+            Some(reference) => coverage(
+                &words(reference).into_iter().collect(),
+                &answer_words.iter().cloned().collect(),
+            ),
+            None => {
+                let relevance = (2.0 * coverage(&terms(&question.content), &answer_terms)).min(1.0);
+                0.5 * relevance + 0.5 * reasoning
+            }
+        };
+
+        Ok(Judgement { correctness, reasoning })
+    }
+}
+
+fn normalize(text: &str) -> String {
+    words(text).join(" ")
+}
+
+/// 1.0 if `answer` equals an accepted answer, else 0.0
+pub fn exact_match(answer: &str, accepted: &[String]) -> f32 {
+    let answer = normalize(answer);
+    if accepted.iter().any(|a| normalize(a) == answer) {
+        1.0
+    } else {
+        0.0
+    }
+}
+
+/// 1.0 within `tolerance` of `expected`, falling off linearly to 0.0 at ten
+/// times the tolerance (or 10% of `expected` when the tolerance is zero)
+pub fn numeric_match(answer: &str, expected: f64, tolerance: f64) -> f32 {
+    let value = answer
+        .split(|c: char| !(c.is_ascii_digit() || c == '.' || c == '-'))
+        .find_map(|token| token.trim_end_matches('.').parse::<f64>().ok());
+    let value = match value {
+        Some(value) => value,
+        None => return 0.0,
+    };
+
+    let error = (value - expected).abs();
+    if error <= tolerance {
+        return 1.0;
+    }
+    let falloff = if tolerance > 0.0 { tolerance * 9.0 } else { (expected.abs() * 0.1).max(f64::EPSILON) };
+    (1.0 - (error - tolerance) / falloff).max(0.0) as f32
+}
+
+/// 1.0 when answered within a quarter of the limit, falling off linearly to
+/// 0.0 at the limit
+pub fn time_efficiency(taken: Duration, limit: Option<Duration>) -> f32 {
+    let limit = limit.unwrap_or(DEFAULT_TIME_LIMIT).as_secs_f32();
+    if limit <= 0.0 {
+        return 0.0;
+    }
+    let ratio = taken.as_secs_f32() / limit;
+    (1.0 - (ratio - 0.25).max(0.0) / 0.75).clamp(0.0, 1.0)
+}
+
+/// Score one answer against its key
+pub async fn score_answer(
+    judge: &dyn AnswerJudge,
+    rubrics: &RubricConfig,
+    question: &AssessmentQuestion,
+    key: &AnswerKey,
+    response: &AssessmentResponse,
+) -> AgentResult<AnswerScore> {
+    let (correctness, reasoning) = match key {
+        AnswerKey::ExactMatch { accepted } => {
+            let reference = accepted.first().map(String::as_str);
+            let judgement = judge.judge(question, &response.answer, reference).await?;
+            (exact_match(&response.answer, accepted), judgement.reasoning)
+        }
+        AnswerKey::Numeric { expected, tolerance } => {
+            let reference = expected.to_string();
+            let judgement = judge.judge(question, &response.answer, Some(&reference)).await?;
+            (numeric_match(&response.answer, *expected, *tolerance), judgement.reasoning)
+        }
+        AnswerKey::FreeText { reference } => {
+            let judgement = judge.judge(question, &response.answer, reference.as_deref()).await?;
+            (judgement.correctness, judgement.reasoning)
+        }
+    };
+    let time_efficiency = time_efficiency(response.time_taken, question.time_limit);
+
+    Ok(AnswerScore {
+        question_id: question.id,
+        category: question.category,
+        difficulty: question.difficulty.value(),
+        correctness,
+        reasoning,
+        time_efficiency,
+        score: rubrics.weights(question.category).combine(correctness, reasoning, time_efficiency),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::AgentLevel;
+
+    fn question(category: QuestionCategory, content: &str) -> AssessmentQuestion {
+        AssessmentQuestion {
+            id: Uuid::new_v4(),
+            category,
+            difficulty: AgentLevel::L5,
+            content: content.to_string(),
+            time_limit: Some(Duration::from_secs(120)),
+        }
+    }
+
+    fn response(answer: &str) -> AssessmentResponse {
+        AssessmentResponse {
+            question_id: Uuid::new_v4(),
+            answer: answer.to_string(),
+            time_taken: Duration::from_secs(60),
+            confidence: 0.5,
+        }
+    }
+
+    async fn scores(question: &AssessmentQuestion, key: &AnswerKey, answers: &[&str]) -> Vec<f32> {
+        let mut scores = Vec::new();
+        for answer in answers {
+            let score = score_answer(&HeuristicJudge, &RubricConfig::default(), question, key, &response(answer))
+                .await
+                .unwrap();
+            scores.push(score.score);
+        }
+        scores
+    }
+
+    fn assert_increasing(scores: &[f32]) {
+        assert!(scores.windows(2).all(|w| w[0] < w[1]), "not increasing: {:?}", scores);
+    }
+
+    #[tokio::test]
+    async fn test_free_text_fixtures_ordered() {
+        let question = question(
+            QuestionCategory::LogicalReasoning,
+            "If all A are B, and all B are C, what can we conclude about A and C?",
+        );
+        let key = AnswerKey::FreeText {
+            reference: Some("All A are C, because set inclusion is transitive".to_string()),
+        };
+        let answers = [
+            "idk",
+            "A and C are related",
+            "All A are C",
+            "All A are C. Since every A is a B and every B is a C, inclusion is transitive, \
+             therefore any member of A must be in C; the converse does not hold because some C \
+             may not be B, so we cannot conclude that all C are A.",
+        ];
+        assert_increasing(&scores(&question, &key, &answers).await);
+    }
+
+    #[tokio::test]
+    async fn test_numeric_fixtures_ordered() {
+        let question = question(QuestionCategory::PatternRecognition, "2, 4, 8, 16, ?");
+        let key = AnswerKey::Numeric { expected: 32.0, tolerance: 0.0 };
+        let answers = [
+            "no idea",
+            "30",
+            "32",
+            "32, because each term doubles the previous one, so the next is 16 * 2 = 32",
+        ];
+        assert_increasing(&scores(&question, &key, &answers).await);
+    }
+
+    #[test]
+    fn test_exact_and_numeric_matching() {
+        let accepted = vec!["E".to_string(), "Eight".to_string()];
+        assert_eq!(exact_match(" e. ", &accepted), 1.0);
+        assert_eq!(exact_match("eight", &accepted), 1.0);
+        assert_eq!(exact_match("seven", &accepted), 0.0);
+
+        assert_eq!(numeric_match("They meet after 2 hours", 2.0, 0.05), 1.0);
+        assert_eq!(numeric_match("2.04", 2.0, 0.05), 1.0);
+        assert!(numeric_match("2.2", 2.0, 0.05) > 0.0);
+        assert!(numeric_match("2.2", 2.0, 0.05) < numeric_match("2.1", 2.0, 0.05));
+        assert_eq!(numeric_match("5", 2.0, 0.05), 0.0);
+        assert_eq!(numeric_match("none", 2.0, 0.05), 0.0);
+    }
+
+    #[test]
+    fn test_time_efficiency() {
+        let limit = Some(Duration::from_secs(100));
+        assert_eq!(time_efficiency(Duration::from_secs(20), limit), 1.0);
+        assert!((time_efficiency(Duration::from_secs(62), limit) - 0.5).abs() < 0.01);
+        assert_eq!(time_efficiency(Duration::from_secs(150), limit), 0.0);
+    }
+
+    struct FixedReply(&'static str);
+
+    #[async_trait]
+    impl CompletionClient for FixedReply {
+        async fn complete(&self, prompt: &str) -> AgentResult<String> {
+            assert!(prompt.contains("REFERENCE ANSWER:\nAll A are C"));
+            Ok(self.0.to_string())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_claude_judge() {
+        let question = question(QuestionCategory::LogicalReasoning, "What follows?");
+        let judge = ClaudeJudge::new(FixedReply("Fair answer.\nCORRECTNESS: 0.9\nREASONING: 1.4"));
+        let judgement = judge.judge(&question, "All A are C", Some("All A are C")).await.unwrap();
+        assert_eq!(judgement, Judgement { correctness: 0.9, reasoning: 1.0 });
+
+        let judge = ClaudeJudge::new(FixedReply("Looks good"));
+        assert!(judge.judge(&question, "All A are C", Some("All A are C")).await.is_err());
+    }
+}
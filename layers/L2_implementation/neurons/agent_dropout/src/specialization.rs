@@ -0,0 +1,103 @@
+//! Per-category specialization learned from evaluations
+//!
+//! Each agent keeps an exponential moving average of its scores in every
+//! question category it was scored in, fed by assessments and peer reviews.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use crate::agent::{AgentLevel, NetworkLayer, QuestionCategory};
+
+/// Weight of a new score in a category's moving average
+pub const SMOOTHING: f32 = 0.3;
+
+/// Average score at which an agent counts as a specialist in a category
+pub const SPECIALIST_SCORE: f32 = 0.7;
+
+/// Moving average of an agent's scores in one category
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct CategorySkill {
+    pub score: f32,
+    /// Scores folded into the average
+    pub observations: u32,
+}
+
+/// An agent's moving average score per question category. The first score
+/// in a category is taken as is.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Specialization {
+    skills: HashMap<QuestionCategory, CategorySkill>,
+}
+
+impl Specialization {
+    /// Fold `score`, clamped to 0..=1, into the category's average
+    pub fn observe(&mut self, category: QuestionCategory, score: f32, smoothing: f32) {
+        let score = score.clamp(0.0, 1.0);
+        self.skills
+            .entry(category)
+            .and_modify(|skill| {
+                skill.score += smoothing * (score - skill.score);
+                skill.observations += 1;
+            })
+            .or_insert(CategorySkill { score, observations: 1 });
+    }
+
+    /// Average score in `category`, `None` if never scored in it
+    pub fn score(&self, category: QuestionCategory) -> Option<f32> {
+        self.skills.get(&category).map(|skill| skill.score)
+    }
+
+    pub fn skill(&self, category: QuestionCategory) -> Option<&CategorySkill> {
+        self.skills.get(&category)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (QuestionCategory, &CategorySkill)> {
+        self.skills.iter().map(|(category, skill)| (*category, skill))
+    }
+
+    /// Category with the highest average
+    pub fn strongest(&self) -> Option<(QuestionCategory, f32)> {
+        self.iter()
+            .map(|(category, skill)| (category, skill.score))
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.skills.is_empty()
+    }
+}
+
+/// How much a layer counts towards an agent's fit for a task: higher layers
+/// are preferred among agents of similar specialization
+pub fn layer_weight(layer: NetworkLayer) -> f32 {
+    match layer {
+        NetworkLayer::Basic => 0.6,
+        NetworkLayer::Intermediate => 0.8,
+        NetworkLayer::Advanced => 1.0,
+    }
+}
+
+/// An agent found for a category, best fit first
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Specialist {
+    pub agent_id: Uuid,
+    pub level: AgentLevel,
+    pub layer: NetworkLayer,
+    /// Average score in the category
+    pub score: f32,
+    /// `score` weighted by [`layer_weight`]
+    pub fit: f32,
+}
+
+/// Spread of specialization in one category across the network
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CategoryDistribution {
+    /// Agents scored in the category
+    pub agents: usize,
+    /// Agents averaging at least [`SPECIALIST_SCORE`]
+    pub specialists: usize,
+    pub mean_score: f32,
+    pub top_score: f32,
+}
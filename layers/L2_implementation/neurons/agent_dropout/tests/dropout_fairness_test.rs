@@ -0,0 +1,223 @@
+//! Dropout decisions account for sample size, so agents of equal skill are
+//! dropped independently of how many evaluations they have had
+
+use agent_dropout::agent::{AgentEntry, AgentNeuron, AssessmentQuestion, AssessmentScores, Evaluatable, MutualEvaluation};
+use agent_dropout::dropout::{DropoutConfig, DropoutDecision, DropoutOrchestrator};
+use agent_dropout::{AgentLevel, AgentProfile, AssessmentResponse, ContextWindow};
+use async_trait::async_trait;
+use chrono::Utc;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::HashSet;
+use std::time::Duration;
+use uuid::Uuid;
+
+/// Agent with a fixed performance score
+struct ScoredAgent {
+    id: Uuid,
+    performance: f32,
+}
+
+#[async_trait]
+impl AgentEntry for ScoredAgent {
+    fn introduce(&self) -> AgentProfile {
+        AgentProfile::new(self.id, AgentLevel::L8)
+    }
+
+    fn self_assess_level(&self) -> AgentLevel {
+        AgentLevel::L8
+    }
+
+    fn context_window_size(&self) -> ContextWindow {
+        ContextWindow::for_level(AgentLevel::L8)
+    }
+
+    async fn accepts_assessment(&self) -> bool {
+        true
+    }
+
+    async fn answer_assessment(&self, question: &AssessmentQuestion) -> AssessmentResponse {
+        AssessmentResponse {
+            question_id: question.id,
+            answer: String::new(),
+            time_taken: Duration::from_secs(10),
+            confidence: self.performance,
+        }
+    }
+}
+
+#[async_trait]
+impl Evaluatable for ScoredAgent {
+    async fn evaluate_peer(&self, peer_id: Uuid, _responses: &[AssessmentResponse]) -> MutualEvaluation {
+        let score = self.performance;
+        MutualEvaluation {
+            evaluator: self.id,
+            evaluated: peer_id,
+            category: None,
+            scores: AssessmentScores { accuracy: score, reasoning: score, creativity: score, speed: score, consistency: score },
+            timestamp: Utc::now(),
+        }
+    }
+
+    async fn accept_evaluation(&self, _evaluation: MutualEvaluation) {}
+}
+
+impl AgentNeuron for ScoredAgent {
+    fn id(&self) -> Uuid {
+        self.id
+    }
+
+    fn performance_score(&self) -> f32 {
+        self.performance
+    }
+
+    fn update_performance(&mut self, score: f32) {
+        self.performance = score;
+    }
+}
+
+fn config() -> DropoutConfig {
+    DropoutConfig {
+        dropout_threshold: 1.0,
+        evaluation_interval: Duration::ZERO,
+        minimum_agent_count: 0,
+        grace_evaluations: 10,
+        min_agents: 0,
+        max_agents: 1000,
+        specialist_threshold: 0.7,
+        specialist_protection: 0.5,
+        success_threshold: 0.5,
+        confidence_z: 1.96,
+    }
+}
+
+/// Register an agent with `evaluations` already recorded at fixed `score`
+async fn register(controller: &DropoutOrchestrator, evaluations: u64, score: f32) -> Uuid {
+    let id = Uuid::new_v4();
+    controller.register_agent(Box::new(ScoredAgent { id, performance: score })).await.unwrap();
+    for _ in 0..evaluations {
+        controller.record_evaluation(id, score).await.unwrap();
+    }
+    id
+}
+
+#[tokio::test]
+async fn test_equal_skill_dropout_is_independent_of_age() {
+    const TRIALS: usize = 40;
+    const PER_GROUP: usize = 25;
+    const SKILL: f64 = 0.6;
+
+    let mut rng = StdRng::seed_from_u64(2190);
+    // Per group: agents, dropped, below the threshold on their point estimate
+    let mut newcomers = (0, 0, 0);
+    let mut veterans = (0, 0, 0);
+
+    for _ in 0..TRIALS {
+        let controller = DropoutOrchestrator::with_config(config());
+        let mut young = HashSet::new();
+        for newcomer in [true, false] {
+            for _ in 0..PER_GROUP {
+                let evaluations = if newcomer { rng.gen_range(10..=16) } else { 300 };
+                let id = Uuid::new_v4();
+                controller.register_agent(Box::new(ScoredAgent { id, performance: SKILL as f32 })).await.unwrap();
+                for _ in 0..evaluations {
+                    let success = if rng.gen_bool(SKILL) { 1.0 } else { 0.0 };
+                    controller.record_evaluation(id, success).await.unwrap();
+                }
+                if newcomer {
+                    young.insert(id);
+                }
+            }
+        }
+        newcomers.0 += PER_GROUP;
+        veterans.0 += PER_GROUP;
+
+        let report = controller.health_check_cycle().await.unwrap();
+        assert_eq!(report.decisions.len(), 2 * PER_GROUP);
+        for decision in &report.decisions {
+            let rationale = decision.rationale();
+            let group = if young.contains(&rationale.agent_id) { &mut newcomers } else { &mut veterans };
+            if matches!(decision, DropoutDecision::Drop(_)) {
+                group.1 += 1;
+            }
+            if rationale.score < rationale.threshold {
+                group.2 += 1;
+            }
+        }
+    }
+
+    let rate = |count: usize, agents: usize| count as f64 / agents as f64;
+
+    // Judged on the point estimate, newcomers would be churned far more often
+    let naive_newcomers = rate(newcomers.2, newcomers.0);
+    let naive_veterans = rate(veterans.2, veterans.0);
+    assert!(naive_newcomers > 0.1, "{}", naive_newcomers);
+    assert!(naive_newcomers > 10.0 * naive_veterans.max(0.001), "{} vs {}", naive_newcomers, naive_veterans);
+
+    // On the confidence bound, neither group is dropped more than the other
+    let dropped_newcomers = rate(newcomers.1, newcomers.0);
+    let dropped_veterans = rate(veterans.1, veterans.0);
+    assert!((dropped_newcomers - dropped_veterans).abs() < 0.02, "{} vs {}", dropped_newcomers, dropped_veterans);
+    assert!(dropped_newcomers < 0.025, "{}", dropped_newcomers);
+}
+
+#[tokio::test]
+async fn test_decisions_explain_grace_and_confident_drops() {
+    let controller = DropoutOrchestrator::with_config(config());
+    let fresh = register(&controller, 9, 0.0).await;
+    let poor = register(&controller, 40, 0.2).await;
+    let decent = register(&controller, 40, 0.45).await;
+    assert!(controller.record_evaluation(Uuid::new_v4(), 1.0).await.is_err());
+
+    let report = controller.health_check_cycle().await.unwrap();
+    assert_eq!(report.dropped_agents, vec![poor]);
+    assert_eq!(report.warnings_issued, 0);
+
+    // Worst bound first
+    let decisions: Vec<_> = report.decisions.iter().map(|decision| decision.rationale().agent_id).collect();
+    assert_eq!(decisions, vec![fresh, poor, decent]);
+
+    let DropoutDecision::Drop(dropped) = &report.decisions[1] else { panic!("{:?}", report.decisions[1]) };
+    assert_eq!((dropped.samples, dropped.threshold), (40, 0.5));
+    assert!((dropped.score - 0.2).abs() < 1e-6);
+    assert!(dropped.score < dropped.bound && dropped.bound < 0.5, "{:?}", dropped);
+
+    // Below the threshold, but not confidently so
+    let DropoutDecision::Keep(kept) = &report.decisions[2] else { panic!("{:?}", report.decisions[2]) };
+    assert!(kept.score < 0.5 && kept.bound > 0.5, "{:?}", kept);
+
+    // Never succeeded, but not past its grace period yet
+    let DropoutDecision::Monitor(monitored) = &report.decisions[0] else { panic!("{:?}", report.decisions[0]) };
+    assert_eq!((monitored.samples, monitored.score), (9, 0.0));
+    assert!(monitored.reason.contains("grace period"), "{}", monitored.reason);
+
+    controller.record_evaluation(fresh, 0.0).await.unwrap();
+    let report = controller.health_check_cycle().await.unwrap();
+    assert_eq!(report.dropped_agents, vec![fresh]);
+
+    // Ages at removal: 40 evaluations in the 32 bucket, 10 in the 8 bucket
+    let stats = controller.network_stats().await;
+    assert_eq!(stats.total_agents, 1);
+    assert_eq!(stats.dropped_age_distribution.into_iter().collect::<Vec<_>>(), vec![(8, 1), (32, 1)]);
+}
+
+#[tokio::test]
+async fn test_dropout_limit_turns_drops_into_warnings() {
+    let controller = DropoutOrchestrator::with_config(DropoutConfig {
+        dropout_threshold: 0.25,
+        minimum_agent_count: 2,
+        ..config()
+    });
+    let worst = register(&controller, 50, 0.1).await;
+    let poor = register(&controller, 50, 0.2).await;
+    for _ in 0..2 {
+        register(&controller, 50, 0.9).await;
+    }
+
+    let report = controller.health_check_cycle().await.unwrap();
+    assert_eq!(report.dropped_agents, vec![worst]);
+    assert_eq!(report.warnings_issued, 1);
+    let DropoutDecision::Monitor(monitored) = &report.decisions[1] else { panic!("{:?}", report.decisions[1]) };
+    assert_eq!(monitored.agent_id, poor);
+    assert!(monitored.reason.contains("dropout limit"), "{}", monitored.reason);
+}
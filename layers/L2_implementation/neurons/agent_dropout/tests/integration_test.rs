@@ -192,7 +192,7 @@ async fn test_dropout_under_pressure() {
 
 #[tokio::test]
 async fn test_evaluation_consistency() {
-    let _engine = EvaluationEngine::new();
+    let engine = EvaluationEngine::new();
     let pool = AssessmentPool::new();
     
     // Get questions
@@ -203,34 +203,46 @@ async fn test_evaluation_consistency() {
         }
     }
     
-    // Create perfect responses
-    let _perfect_responses: Vec<_> = questions.iter().map(|q| {
-        agent_dropout::AssessmentResponse {
+    // Create perfect responses: on topic, with explicit reasoning steps
+    let perfect_responses: Vec<_> = questions.iter().map(|q| {
+        let response = agent_dropout::AssessmentResponse {
             question_id: q.id,
-            answer: "Perfect answer with detailed explanation".to_string(),
+            answer: format!(
+                "{} To answer this, break the problem into parts, because each part can be checked \
+                 on its own. If every part holds, then the whole holds, therefore the argument is \
+                 sound; since no counterexample survives the checks, we can commit to the \
+                 conclusion and explain the trade-offs it implies for the original question.",
+                q.content
+            ),
             time_taken: Duration::from_secs(45),
             confidence: 0.95,
-        }
+        };
+        (q.clone(), response)
     }).collect();
     
     // Create poor responses
-    let _poor_responses: Vec<_> = questions.iter().map(|q| {
-        agent_dropout::AssessmentResponse {
+    let poor_responses: Vec<_> = questions.iter().map(|q| {
+        let response = agent_dropout::AssessmentResponse {
             question_id: q.id,
             answer: "idk".to_string(),
             time_taken: Duration::from_secs(5),
             confidence: 0.1,
-        }
+        };
+        (q.clone(), response)
     }).collect();
     
-    // Simulate evaluation (since evaluate_responses doesn't exist)
-    let perfect_score = 0.95; // High score for perfect responses
-    let poor_score = 0.25; // Low score for poor responses
+    let perfect = engine.evaluate(&pool, &perfect_responses).await.unwrap();
+    let poor = engine.evaluate(&pool, &poor_responses).await.unwrap();
     
     // Perfect should score much higher
-    assert!(perfect_score > 0.8);
-    assert!(poor_score < 0.3);
-    assert!(perfect_score > poor_score + 0.4);
+    assert!(perfect.overall_score > 0.8, "{}", perfect.overall_score);
+    assert!(poor.overall_score < 0.3, "{}", poor.overall_score);
+    assert!(perfect.overall_score > poor.overall_score + 0.4);
+    assert!(perfect.level_estimate > poor.level_estimate);
+    
+    // Uniform quality across repeated difficulties
+    assert!(perfect.consistency_score > 0.8, "{}", perfect.consistency_score);
+    assert!(poor.consistency_score > 0.8, "{}", poor.consistency_score);
 }
 
 #[tokio::test]
@@ -0,0 +1,210 @@
+//! Topology persistence, restore and graph export
+
+use agent_dropout::network::{AgentNode, ConnectionEdge};
+use agent_dropout::persistence::PersistedConnection;
+use agent_dropout::{
+    AgentLevel, AgentProfile, ConnectionDecay, FileTopologyStore, NetworkLayer, NetworkTopology,
+    TopologySnapshot, TopologyStore,
+};
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use std::sync::Arc;
+use uuid::Uuid;
+
+fn store(dir: &tempfile::TempDir) -> Arc<dyn TopologyStore> {
+    Arc::new(FileTopologyStore::open(dir.path()).unwrap())
+}
+
+fn node(id: u128, level: AgentLevel, connections_count: usize) -> AgentNode {
+    AgentNode {
+        id: Uuid::from_u128(id),
+        level,
+        layer: level.layer(),
+        connections_count,
+        specialization: Default::default(),
+    }
+}
+
+fn edge(strength: f32, updated_at: DateTime<Utc>) -> ConnectionEdge {
+    ConnectionEdge {
+        strength,
+        interaction_count: 2,
+        last_interaction: updated_at,
+        updated_at,
+    }
+}
+
+fn connection(from: u128, to: u128, edge: ConnectionEdge) -> PersistedConnection {
+    PersistedConnection {
+        from: Uuid::from_u128(from),
+        to: Uuid::from_u128(to),
+        edge,
+    }
+}
+
+async fn build(topology: &NetworkTopology) -> Vec<Uuid> {
+    let levels = [AgentLevel::L2, AgentLevel::L4, AgentLevel::L7, AgentLevel::L9, AgentLevel::L12, AgentLevel::L18];
+    let mut ids = Vec::new();
+    for level in levels {
+        let profile = AgentProfile::new(Uuid::new_v4(), level);
+        topology.place_agent(&profile).await;
+        ids.push(profile.id);
+    }
+    topology.connect_agents(ids[0], ids[5], 0.3).await;
+    topology.update_connection(ids[0], ids[1], true).await;
+    topology.update_connection(ids[2], ids[3], false).await;
+    ids
+}
+
+#[tokio::test]
+async fn test_round_trip_through_journal() {
+    let dir = tempfile::tempdir().unwrap();
+    let topology = NetworkTopology::restore(store(&dir), None).await.unwrap();
+    let ids = build(&topology).await;
+    topology.remove_agent(ids[3]).await;
+    assert!(topology.move_agent(ids[1], AgentLevel::L11).await);
+    let stats = topology.get_network_stats().await;
+    assert_eq!(stats.layer_distribution[&NetworkLayer::Advanced], 3);
+    drop(topology);
+
+    let restored = NetworkTopology::restore(store(&dir), None).await.unwrap();
+    assert_eq!(restored.get_network_stats().await, stats);
+    assert!(restored.are_connected(ids[0], ids[5]).await);
+    assert!(!restored.are_connected(ids[2], ids[3]).await);
+
+    // Restoring compacted the journal; a third load reads the snapshot alone
+    let again = NetworkTopology::restore(store(&dir), None).await.unwrap();
+    assert_eq!(again.get_network_stats().await, stats);
+}
+
+#[tokio::test]
+async fn test_round_trip_preserves_weights() {
+    let dir = tempfile::tempdir().unwrap();
+    let topology = NetworkTopology::restore(store(&dir), None).await.unwrap();
+    build(&topology).await;
+    topology.compact().await.unwrap();
+    let before = topology.snapshot().await;
+    drop(topology);
+
+    let after = NetworkTopology::restore(store(&dir), None).await.unwrap().snapshot().await;
+    assert_eq!(after.agents.len(), before.agents.len());
+    assert_eq!(after.connections.len(), before.connections.len());
+    for (a, b) in after.connections.iter().zip(&before.connections) {
+        assert_eq!((a.from, a.to), (b.from, b.to));
+        assert_eq!(a.edge.strength, b.edge.strength);
+        assert_eq!(a.edge.interaction_count, b.edge.interaction_count);
+        assert_eq!(a.edge.updated_at, b.edge.updated_at);
+    }
+}
+
+#[tokio::test]
+async fn test_stale_connections_decay_on_restore() {
+    let dir = tempfile::tempdir().unwrap();
+    let now = Utc::now();
+    let snapshot = TopologySnapshot {
+        agents: vec![node(1, AgentLevel::L3, 2), node(2, AgentLevel::L4, 2), node(3, AgentLevel::L5, 2)],
+        connections: vec![
+            connection(1, 2, edge(0.8, now - Duration::days(14))),
+            connection(2, 3, edge(0.15, now - Duration::days(70))),
+            connection(1, 3, edge(0.6, now)),
+        ],
+        saved_at: None,
+    };
+    let store = store(&dir);
+    store.compact(&snapshot).unwrap();
+
+    let decay = ConnectionDecay {
+        half_life: Duration::days(7),
+        min_strength: 0.1,
+    };
+    let restored = NetworkTopology::restore(store, Some(decay)).await.unwrap().snapshot().await;
+    let strengths: Vec<f32> = restored.connections.iter().map(|c| c.edge.strength).collect();
+    assert!((strengths[0] - 0.2).abs() < 0.01, "{:?}", strengths);
+    assert_eq!(strengths[1], 0.1);
+    assert!((strengths[2] - 0.6).abs() < 0.001, "{:?}", strengths);
+    assert!(restored.connections[0].edge.updated_at > now - Duration::days(1));
+}
+
+#[tokio::test]
+async fn test_structure_stats() {
+    let at = Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap();
+    // Triangle 1-2-3 plus a pendant 4 hanging off 3
+    let topology = NetworkTopology::from_snapshot(TopologySnapshot {
+        agents: vec![
+            node(1, AgentLevel::L1, 2),
+            node(2, AgentLevel::L2, 2),
+            node(3, AgentLevel::L3, 3),
+            node(4, AgentLevel::L4, 1),
+        ],
+        connections: vec![
+            connection(1, 2, edge(0.5, at)),
+            connection(2, 3, edge(0.5, at)),
+            connection(3, 1, edge(0.5, at)),
+            connection(3, 4, edge(0.5, at)),
+            connection(4, 3, edge(0.5, at)),
+        ],
+        saved_at: None,
+    });
+
+    let stats = topology.get_network_stats().await;
+    assert_eq!(stats.degree_distribution.into_iter().collect::<Vec<_>>(), vec![(1, 1), (2, 2), (3, 1)]);
+    // Locals: 1, 1, 1/3, 0
+    assert!((stats.clustering_coefficient - (7.0 / 12.0)).abs() < 1e-6);
+    assert_eq!(stats.layer_distribution[&NetworkLayer::Basic], 4);
+}
+
+#[tokio::test]
+async fn test_export_snapshot() {
+    let at = Utc.with_ymd_and_hms(2025, 1, 1, 12, 0, 0).unwrap();
+    let topology = NetworkTopology::from_snapshot(TopologySnapshot {
+        agents: vec![node(1, AgentLevel::L3, 1), node(2, AgentLevel::L8, 1)],
+        connections: vec![connection(1, 2, edge(0.75, at)), connection(2, 1, edge(0.5, at))],
+        saved_at: None,
+    });
+
+    assert_eq!(
+        topology.export_dot().await,
+        r#"digraph agents {
+  "00000000-0000-0000-0000-000000000001" [label="L3", layer="Basic", connections=1];
+  "00000000-0000-0000-0000-000000000002" [label="L8", layer="Intermediate", connections=1];
+  "00000000-0000-0000-0000-000000000001" -> "00000000-0000-0000-0000-000000000002" [weight=0.75, interactions=2];
+  "00000000-0000-0000-0000-000000000002" -> "00000000-0000-0000-0000-000000000001" [weight=0.5, interactions=2];
+}
+"#
+    );
+
+    assert_eq!(
+        topology.export_graphml().await,
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<graphml xmlns="http://graphml.graphdrawing.org/xmlns">
+  <key id="level" for="node" attr.name="level" attr.type="int"/>
+  <key id="layer" for="node" attr.name="layer" attr.type="string"/>
+  <key id="connections" for="node" attr.name="connections" attr.type="int"/>
+  <key id="weight" for="edge" attr.name="weight" attr.type="double"/>
+  <key id="interactions" for="edge" attr.name="interactions" attr.type="long"/>
+  <key id="updated_at" for="edge" attr.name="updated_at" attr.type="string"/>
+  <graph id="agents" edgedefault="directed">
+    <node id="00000000-0000-0000-0000-000000000001">
+      <data key="level">3</data>
+      <data key="layer">Basic</data>
+      <data key="connections">1</data>
+    </node>
+    <node id="00000000-0000-0000-0000-000000000002">
+      <data key="level">8</data>
+      <data key="layer">Intermediate</data>
+      <data key="connections">1</data>
+    </node>
+    <edge id="e0" source="00000000-0000-0000-0000-000000000001" target="00000000-0000-0000-0000-000000000002">
+      <data key="weight">0.75</data>
+      <data key="interactions">2</data>
+      <data key="updated_at">2025-01-01T12:00:00+00:00</data>
+    </edge>
+    <edge id="e1" source="00000000-0000-0000-0000-000000000002" target="00000000-0000-0000-0000-000000000001">
+      <data key="weight">0.5</data>
+      <data key="interactions">2</data>
+      <data key="updated_at">2025-01-01T12:00:00+00:00</data>
+    </edge>
+  </graph>
+</graphml>
+"#
+    );
+}
@@ -0,0 +1,145 @@
+//! A 10,000-agent network under continuous evaluation and dropout
+//!
+//! Budgets for an optimized build (`cargo test --release --test scale_test`),
+//! against what one run took on a laptop:
+//!
+//! - placing 10,000 agents: 1 s (took 0.11 s)
+//! - 100 cycles of evaluating 200 agents, 500 interactions, dropping and
+//!   replacing the bottom 1% and reading the network stats: 3 s (took 0.55 s)
+//! - peak heap: 64 MiB (took 40 MiB)
+//!
+//! Unoptimized builds ran about 7x slower and get [`DEBUG_SLOWDOWN`] times
+//! the time budgets; the memory budget is the same.
+
+use agent_dropout::{AgentLevel, AgentProfile, EvaluationResult, NetworkTopology, QuestionCategory};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+const AGENTS: usize = 10_000;
+const CYCLES: usize = 100;
+const EVALUATIONS_PER_CYCLE: usize = 200;
+const INTERACTIONS_PER_CYCLE: usize = 500;
+const DROPOUT_RATE: f32 = 0.01;
+
+const PLACE_BUDGET: Duration = Duration::from_secs(1);
+const CYCLES_BUDGET: Duration = Duration::from_secs(3);
+const PEAK_HEAP_BUDGET: usize = 64 << 20;
+const DEBUG_SLOWDOWN: u32 = 10;
+
+/// System allocator keeping track of the most heap in use at once
+struct PeakAlloc;
+
+static IN_USE: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for PeakAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            let in_use = IN_USE.fetch_add(layout.size(), Ordering::Relaxed) + layout.size();
+            PEAK.fetch_max(in_use, Ordering::Relaxed);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        IN_USE.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: PeakAlloc = PeakAlloc;
+
+fn budget(optimized: Duration) -> Duration {
+    if cfg!(debug_assertions) {
+        optimized * DEBUG_SLOWDOWN
+    } else {
+        optimized
+    }
+}
+
+/// A new agent and the quality it shows in evaluations
+fn agent(rng: &mut StdRng) -> (AgentProfile, f32) {
+    let level = AgentLevel::from_value(rng.gen_range(1..=20)).unwrap();
+    (AgentProfile::new(Uuid::from_u128(rng.gen()), level), rng.gen_range(0.0..1.0))
+}
+
+#[tokio::test]
+async fn test_ten_thousand_agents_within_budget() {
+    let mut rng = StdRng::seed_from_u64(10_000);
+    let categories = [
+        QuestionCategory::LogicalReasoning,
+        QuestionCategory::PatternRecognition,
+        QuestionCategory::SystemsThinking,
+    ];
+    PEAK.store(IN_USE.load(Ordering::Relaxed), Ordering::Relaxed);
+    let baseline = IN_USE.load(Ordering::Relaxed);
+
+    let topology = NetworkTopology::new();
+    let mut quality: HashMap<Uuid, f32> = HashMap::with_capacity(AGENTS);
+    let mut links: Vec<(Uuid, Uuid)> = Vec::new();
+
+    let start = Instant::now();
+    for _ in 0..AGENTS {
+        let (profile, score) = agent(&mut rng);
+        let position = topology.place_agent(&profile).await;
+        links.extend(position.initial_connections.iter().map(|peer| (profile.id, *peer)));
+        quality.insert(profile.id, score);
+    }
+    let placed_in = start.elapsed();
+
+    let start = Instant::now();
+    for _ in 0..CYCLES {
+        let ids: Vec<Uuid> = quality.keys().copied().collect();
+        for agent in ids.choose_multiple(&mut rng, EVALUATIONS_PER_CYCLE) {
+            let score = (quality[agent] + rng.gen_range(-0.1..0.1)).clamp(0.0, 1.0);
+            let result = EvaluationResult {
+                overall_score: score,
+                level_estimate: AgentLevel::L10,
+                category_scores: HashMap::from([(*categories.choose(&mut rng).unwrap(), score)]),
+                time_efficiency: 1.0,
+                consistency_score: 1.0,
+            };
+            topology.record_evaluation(*agent, &result).await.unwrap();
+        }
+
+        // Links to dropped agents are no-ops, as in a live network
+        for &(from, to) in links.choose_multiple(&mut rng, INTERACTIONS_PER_CYCLE) {
+            let success = quality.get(&from).is_some_and(|score| rng.gen::<f32>() < *score);
+            topology.update_connection(from, to, success).await;
+        }
+
+        let mut ranked: Vec<(Uuid, f32)> = quality.iter().map(|(id, score)| (*id, *score)).collect();
+        ranked.sort_by(|a, b| a.1.total_cmp(&b.1).then(a.0.cmp(&b.0)));
+        for (dropped, _) in ranked.iter().take((AGENTS as f32 * DROPOUT_RATE) as usize) {
+            topology.remove_agent(*dropped).await;
+            quality.remove(dropped);
+
+            let (profile, score) = agent(&mut rng);
+            let position = topology.place_agent(&profile).await;
+            links.extend(position.initial_connections.iter().map(|peer| (profile.id, *peer)));
+            quality.insert(profile.id, score);
+        }
+
+        let stats = topology.get_network_stats().await;
+        assert_eq!(stats.total_agents, AGENTS);
+        assert_eq!(stats.degree_distribution.values().sum::<usize>(), AGENTS);
+    }
+    let cycled_in = start.elapsed();
+    let peak = PEAK.load(Ordering::Relaxed) - baseline;
+
+    println!(
+        "placed {} agents in {:?}, {} cycles in {:?}, peak heap {} KiB",
+        AGENTS, placed_in, CYCLES, cycled_in, peak >> 10
+    );
+    assert!(placed_in < budget(PLACE_BUDGET), "placing took {:?}", placed_in);
+    assert!(cycled_in < budget(CYCLES_BUDGET), "cycles took {:?}", cycled_in);
+    assert!(peak < PEAK_HEAP_BUDGET, "peak heap was {} KiB", peak >> 10);
+}
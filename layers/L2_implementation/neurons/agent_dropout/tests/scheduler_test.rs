@@ -0,0 +1,318 @@
+//! Periodic re-assessment of placed agents
+
+use agent_dropout::agent::{AgentEntry, AgentNeuron, AssessmentQuestion, AssessmentScores, Evaluatable, MutualEvaluation};
+use agent_dropout::dropout::{DropoutConfig, DropoutDecision, DropoutOrchestrator};
+use agent_dropout::scoring::Judgement;
+use agent_dropout::{
+    AgentLevel, AgentProfile, AgentResult, AnswerJudge, AnswerKey, AssessmentPool, AssessmentResponse,
+    AssessmentScheduler, ContextWindow, EvaluationEngine, FileScheduleStore, NetworkLayer, NetworkTopology,
+    RubricConfig, ScheduleConfig, ScheduleStore,
+};
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use std::sync::{Arc, Mutex};
+use uuid::Uuid;
+
+const UNSOLVED: &str = "I don't know";
+
+/// Agent solving exactly the questions at or below its skill, quickly
+struct SyntheticAgent {
+    id: Uuid,
+    skill: Mutex<f32>,
+    pool: Arc<AssessmentPool>,
+}
+
+impl SyntheticAgent {
+    fn new(skill: f32, pool: Arc<AssessmentPool>) -> Arc<Self> {
+        Arc::new(Self { id: Uuid::new_v4(), skill: Mutex::new(skill), pool })
+    }
+
+    fn set_skill(&self, skill: f32) {
+        *self.skill.lock().unwrap() = skill;
+    }
+}
+
+#[async_trait]
+impl AgentEntry for SyntheticAgent {
+    fn introduce(&self) -> AgentProfile {
+        AgentProfile::new(self.id, self.self_assess_level())
+    }
+
+    fn self_assess_level(&self) -> AgentLevel {
+        AgentLevel::from_value(self.skill.lock().unwrap().round().clamp(1.0, 20.0) as u8).unwrap()
+    }
+
+    fn context_window_size(&self) -> ContextWindow {
+        ContextWindow::for_level(self.self_assess_level())
+    }
+
+    async fn accepts_assessment(&self) -> bool {
+        true
+    }
+
+    async fn answer_assessment(&self, question: &AssessmentQuestion) -> AssessmentResponse {
+        let solved = question.difficulty.value() as f32 <= *self.skill.lock().unwrap();
+        let answer = match (solved, self.pool.answer_key(question.id)) {
+            (false, _) => UNSOLVED.to_string(),
+            (true, AnswerKey::Numeric { expected, .. }) => expected.to_string(),
+            (true, AnswerKey::ExactMatch { accepted }) => accepted[0].clone(),
+            (true, AnswerKey::FreeText { .. }) => "Solved, with reasons".to_string(),
+        };
+        AssessmentResponse {
+            question_id: question.id,
+            answer,
+            time_taken: if solved { std::time::Duration::from_secs(10) } else { question.time_limit.unwrap() },
+            confidence: 0.8,
+        }
+    }
+}
+
+#[async_trait]
+impl Evaluatable for SyntheticAgent {
+    async fn evaluate_peer(&self, peer_id: Uuid, _responses: &[AssessmentResponse]) -> MutualEvaluation {
+        let score = 0.5;
+        MutualEvaluation {
+            evaluator: self.id,
+            evaluated: peer_id,
+            category: None,
+            scores: AssessmentScores { accuracy: score, reasoning: score, creativity: score, speed: score, consistency: score },
+            timestamp: Utc::now(),
+        }
+    }
+
+    async fn accept_evaluation(&self, _evaluation: MutualEvaluation) {}
+}
+
+impl AgentNeuron for SyntheticAgent {
+    fn id(&self) -> Uuid {
+        self.id
+    }
+
+    fn performance_score(&self) -> f32 {
+        *self.skill.lock().unwrap() / 20.0
+    }
+
+    fn update_performance(&mut self, score: f32) {
+        *self.skill.get_mut().unwrap() = score * 20.0;
+    }
+}
+
+/// Full marks for any attempt, none for giving up
+struct AttemptJudge;
+
+#[async_trait]
+impl AnswerJudge for AttemptJudge {
+    async fn judge(&self, _question: &AssessmentQuestion, answer: &str, _reference: Option<&str>) -> AgentResult<Judgement> {
+        let value = if answer == UNSOLVED { 0.0 } else { 1.0 };
+        Ok(Judgement { correctness: value, reasoning: value })
+    }
+}
+
+struct Network {
+    pool: Arc<AssessmentPool>,
+    engine: Arc<EvaluationEngine>,
+    topology: Arc<NetworkTopology>,
+}
+
+impl Network {
+    fn new() -> Self {
+        Self {
+            pool: Arc::new(AssessmentPool::new()),
+            engine: Arc::new(EvaluationEngine::with_judge(Arc::new(AttemptJudge), RubricConfig::default())),
+            topology: Arc::new(NetworkTopology::new()),
+        }
+    }
+
+    async fn place(&self, agent: &Arc<SyntheticAgent>, level: AgentLevel) {
+        self.topology.place_agent(&AgentProfile::new(agent.id, level)).await;
+    }
+
+    fn scheduler(&self, store: Arc<dyn ScheduleStore>) -> AssessmentScheduler {
+        let (pool, engine, topology) = (self.pool.clone(), self.engine.clone(), self.topology.clone());
+        AssessmentScheduler::restore(store, ScheduleConfig::default(), pool, engine, topology).unwrap()
+    }
+
+    async fn layer_of(&self, agent_id: Uuid) -> Option<NetworkLayer> {
+        let snapshot = self.topology.snapshot().await;
+        snapshot.agents.iter().find(|node| node.id == agent_id).map(|node| node.layer)
+    }
+}
+
+fn start() -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap()
+}
+
+/// True skill on `day`: climbing from 4 to 16 over two months, holding,
+/// then sliding to 7
+fn drifting_skill(day: f32) -> f32 {
+    match day {
+        d if d < 60.0 => 4.0 + 12.0 * d / 60.0,
+        d if d < 120.0 => 16.0,
+        d if d < 180.0 => 16.0 - 9.0 * (d - 120.0) / 60.0,
+        _ => 7.0,
+    }
+}
+
+#[tokio::test]
+async fn test_levels_track_drifting_skill() {
+    let dir = tempfile::tempdir().unwrap();
+    let network = Network::new();
+    let agent = SyntheticAgent::new(drifting_skill(0.0), network.pool.clone());
+    network.place(&agent, AgentLevel::L4).await;
+    let mut scheduler = network.scheduler(Arc::new(FileScheduleStore::open(dir.path()).unwrap()));
+    scheduler.register(agent.clone(), AgentLevel::L4, start());
+
+    let mut assessments = Vec::new();
+    for step in 0..=(240 * 2) {
+        let day = step as f32 / 2.0;
+        let now = start() + Duration::hours(12 * step);
+        agent.set_skill(drifting_skill(day));
+
+        // Restart halfway through; the schedule carries over
+        if step == 180 {
+            let before = scheduler.entry(agent.id).unwrap();
+            drop(scheduler);
+            scheduler = network.scheduler(Arc::new(FileScheduleStore::open(dir.path()).unwrap()));
+            assert_eq!(scheduler.entry(agent.id), Some(before.clone()));
+            scheduler.register(agent.clone(), AgentLevel::L4, now);
+            assert_eq!(scheduler.entry(agent.id), Some(before));
+        }
+
+        for result in scheduler.run_due(now).await {
+            assessments.push((now, day, result));
+        }
+    }
+
+    let level_on = |day: f32| {
+        assessments
+            .iter()
+            .take_while(|(_, at, _)| *at <= day)
+            .last()
+            .map(|(_, _, result)| result.level.value() as f32)
+            .unwrap()
+    };
+    for day in [100.0, 115.0, 220.0, 240.0] {
+        let (level, skill) = (level_on(day), drifting_skill(day));
+        assert!((level - skill).abs() <= 3.0, "day {}: level {} for skill {}", day, level, skill);
+    }
+    assert_eq!(network.layer_of(agent.id).await, Some(AgentLevel::from_value(level_on(240.0) as u8).unwrap().layer()));
+
+    // Stale agents get re-assessed less often the higher they sit
+    for pair in assessments.windows(2) {
+        let ((previous_at, _, previous), (at, _, _)) = (&pair[0], &pair[1]);
+        let gap = *at - *previous_at;
+        assert!(gap >= ScheduleConfig::default().interval(previous.level.layer()), "{:?}", previous);
+        if previous.moved {
+            assert!(gap >= ScheduleConfig::default().cooldown);
+        }
+    }
+    let advanced = assessments.iter().filter(|(_, _, result)| result.level.layer() == NetworkLayer::Advanced).count();
+    let basic = assessments.iter().filter(|(_, _, result)| result.level.layer() == NetworkLayer::Basic).count();
+    assert!(advanced > 0 && basic > 0);
+}
+
+#[tokio::test]
+async fn test_hysteresis_and_cooldown() {
+    let network = Network::new();
+    let config = ScheduleConfig { hysteresis: 3, ..ScheduleConfig::default() };
+    let scheduler = AssessmentScheduler::new(config, network.pool.clone(), network.engine.clone(), network.topology.clone());
+
+    // An agent in the right place stays there
+    let steady = SyntheticAgent::new(10.0, network.pool.clone());
+    network.place(&steady, AgentLevel::L10).await;
+    scheduler.register(steady.clone(), AgentLevel::L10, start());
+    for _ in 0..10 {
+        let result = scheduler.reassess(steady.id, start()).await.unwrap();
+        assert!(!result.moved, "{:?}", result);
+        assert_eq!(result.level, AgentLevel::L10);
+    }
+
+    // One that outgrew its layer moves, then sits out the cool-down
+    let grown = SyntheticAgent::new(18.0, network.pool.clone());
+    network.place(&grown, AgentLevel::L3).await;
+    scheduler.register(grown.clone(), AgentLevel::L3, start());
+    assert_eq!(scheduler.entry(grown.id).unwrap().due_at, start() + Duration::days(1));
+    let result = scheduler.reassess(grown.id, start()).await.unwrap();
+    assert!(result.moved);
+    assert_eq!(result.level.layer(), NetworkLayer::Advanced);
+    assert_eq!(network.layer_of(grown.id).await, Some(NetworkLayer::Advanced));
+    assert_eq!(result.next_due, start() + Duration::days(7));
+
+    // A move made elsewhere starts a cool-down too
+    scheduler.record_move(steady.id, AgentLevel::L12, start() + Duration::days(2));
+    assert!(!scheduler.due(start() + Duration::days(4)).contains(&steady.id));
+    assert_eq!(scheduler.due(start() + Duration::days(5)), vec![steady.id]);
+    assert!(scheduler.reassess(Uuid::new_v4(), start()).await.is_err());
+}
+
+#[tokio::test]
+async fn test_schedule_survives_restart() {
+    let dir = tempfile::tempdir().unwrap();
+    let store = || -> Arc<dyn ScheduleStore> { Arc::new(FileScheduleStore::open(dir.path()).unwrap()) };
+    let network = Network::new();
+    let agents: Vec<_> = [3.0, 8.0, 15.0].into_iter().map(|skill| SyntheticAgent::new(skill, network.pool.clone())).collect();
+
+    let scheduler = network.scheduler(store());
+    for (agent, level) in agents.iter().zip([AgentLevel::L3, AgentLevel::L8, AgentLevel::L15]) {
+        network.place(agent, level).await;
+        scheduler.register(agent.clone(), level, start());
+    }
+    scheduler.run_due(start() + Duration::days(1)).await;
+    scheduler.unregister(agents[1].id);
+    let state = scheduler.state().agents;
+    drop(scheduler);
+
+    let restored = network.scheduler(store());
+    assert_eq!(restored.state().agents, state);
+    assert_eq!(state.len(), 2);
+    assert_eq!(state[0].last_assessed, Some(start() + Duration::days(1)));
+    assert_eq!(state[1].due_at, start() + Duration::days(7));
+
+    // Without its handle, a due agent waits instead of failing
+    assert_eq!(restored.due(start() + Duration::days(2)), vec![agents[0].id]);
+    assert!(restored.run_due(start() + Duration::days(2)).await.is_empty());
+}
+
+#[tokio::test]
+async fn test_reassessments_drive_dropout_decisions() {
+    let network = Network::new();
+    let dropout = Arc::new(DropoutOrchestrator::with_config(DropoutConfig {
+        evaluation_interval: std::time::Duration::ZERO,
+        minimum_agent_count: 0,
+        dropout_threshold: 1.0,
+        ..DropoutConfig::default()
+    }));
+    let config = ScheduleConfig::default();
+    let scheduler = AssessmentScheduler::new(config, network.pool.clone(), network.engine.clone(), network.topology.clone())
+        .with_dropout(dropout.clone());
+
+    let strong = SyntheticAgent::new(20.0, network.pool.clone());
+    let weak = SyntheticAgent::new(1.0, network.pool.clone());
+    // Re-levelled by the scheduler, but not one the orchestrator manages
+    let outsider = SyntheticAgent::new(10.0, network.pool.clone());
+    for (agent, level) in [(&strong, AgentLevel::L20), (&weak, AgentLevel::L1), (&outsider, AgentLevel::L10)] {
+        network.place(agent, level).await;
+        scheduler.register(agent.clone(), level, start());
+    }
+    for agent in [&strong, &weak] {
+        let twin = SyntheticAgent { id: agent.id, skill: Mutex::new(*agent.skill.lock().unwrap()), pool: network.pool.clone() };
+        dropout.register_agent(Box::new(twin)).await.unwrap();
+    }
+
+    let rounds = DropoutConfig::default().grace_evaluations * 2;
+    for _ in 0..rounds {
+        for agent in [&strong, &weak, &outsider] {
+            scheduler.reassess(agent.id, start()).await.unwrap();
+        }
+    }
+
+    let report = dropout.health_check_cycle().await.unwrap();
+    assert_eq!(report.evaluated_agents, 2);
+    for decision in &report.decisions {
+        assert_eq!(decision.rationale().samples, rounds, "{:?}", decision);
+    }
+    let decision = |agent_id: Uuid| report.decisions.iter().find(|d| d.rationale().agent_id == agent_id).unwrap();
+    assert!(matches!(decision(weak.id), DropoutDecision::Drop(_)), "{:?}", decision(weak.id));
+    assert!(matches!(decision(strong.id), DropoutDecision::Keep(_)), "{:?}", decision(strong.id));
+    assert_eq!(report.dropped_agents, vec![weak.id]);
+}
@@ -0,0 +1,229 @@
+//! Specialization learning, specialist routing and dropout protection
+
+use agent_dropout::agent::{AgentEntry, AgentNeuron, AssessmentQuestion, AssessmentScores, Evaluatable, MutualEvaluation};
+use agent_dropout::dropout::{DropoutConfig, DropoutOrchestrator};
+use agent_dropout::{
+    AgentLevel, AgentProfile, AssessmentResponse, ContextWindow, EvaluationEngine, EvaluationResult,
+    FileTopologyStore, NetworkTopology, QuestionCategory,
+};
+use async_trait::async_trait;
+use chrono::Utc;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use uuid::Uuid;
+
+const ASSESSED: [QuestionCategory; 3] = [
+    QuestionCategory::LogicalReasoning,
+    QuestionCategory::PatternRecognition,
+    QuestionCategory::EthicalDilemmas,
+];
+
+/// Agent with a fixed performance score
+struct ScoredAgent {
+    id: Uuid,
+    level: AgentLevel,
+    performance: f32,
+}
+
+#[async_trait]
+impl AgentEntry for ScoredAgent {
+    fn introduce(&self) -> AgentProfile {
+        AgentProfile::new(self.id, self.level)
+    }
+
+    fn self_assess_level(&self) -> AgentLevel {
+        self.level
+    }
+
+    fn context_window_size(&self) -> ContextWindow {
+        ContextWindow::for_level(self.level)
+    }
+
+    async fn accepts_assessment(&self) -> bool {
+        true
+    }
+
+    async fn answer_assessment(&self, question: &AssessmentQuestion) -> AssessmentResponse {
+        AssessmentResponse {
+            question_id: question.id,
+            answer: String::new(),
+            time_taken: Duration::from_secs(10),
+            confidence: self.performance,
+        }
+    }
+}
+
+#[async_trait]
+impl Evaluatable for ScoredAgent {
+    async fn evaluate_peer(&self, peer_id: Uuid, _responses: &[AssessmentResponse]) -> MutualEvaluation {
+        let score = self.performance;
+        MutualEvaluation {
+            evaluator: self.id,
+            evaluated: peer_id,
+            category: None,
+            scores: AssessmentScores { accuracy: score, reasoning: score, creativity: score, speed: score, consistency: score },
+            timestamp: Utc::now(),
+        }
+    }
+
+    async fn accept_evaluation(&self, _evaluation: MutualEvaluation) {}
+}
+
+impl AgentNeuron for ScoredAgent {
+    fn id(&self) -> Uuid {
+        self.id
+    }
+
+    fn performance_score(&self) -> f32 {
+        self.performance
+    }
+
+    fn update_performance(&mut self, score: f32) {
+        self.performance = score;
+    }
+}
+
+/// Place an agent and assess it six times, scoring `strength` in its
+/// category and `base` elsewhere, give or take 0.05
+async fn assess(topology: &NetworkTopology, level: AgentLevel, strong: Option<QuestionCategory>, strength: f32, base: f32) -> Uuid {
+    let profile = AgentProfile::new(Uuid::new_v4(), level);
+    topology.place_agent(&profile).await;
+    for round in 0..6 {
+        let jitter = if round % 2 == 0 { 0.05 } else { -0.05 };
+        let category_scores: HashMap<_, _> = ASSESSED
+            .iter()
+            .map(|&category| {
+                let score = if Some(category) == strong { strength } else { base };
+                (category, score + jitter)
+            })
+            .collect();
+        let result = EvaluationResult {
+            overall_score: base,
+            level_estimate: level,
+            category_scores,
+            time_efficiency: 1.0,
+            consistency_score: 1.0,
+        };
+        assert!(topology.record_evaluation(profile.id, &result).await.is_some());
+    }
+    profile.id
+}
+
+#[tokio::test]
+async fn test_specialists_found_by_category() {
+    let dir = tempfile::tempdir().unwrap();
+    let topology = NetworkTopology::restore(Arc::new(FileTopologyStore::open(dir.path()).unwrap()), None).await.unwrap();
+
+    let logician = assess(&topology, AgentLevel::L12, Some(QuestionCategory::LogicalReasoning), 0.9, 0.4).await;
+    let spotter = assess(&topology, AgentLevel::L7, Some(QuestionCategory::PatternRecognition), 0.95, 0.4).await;
+    let basic_ethicist = assess(&topology, AgentLevel::L3, Some(QuestionCategory::EthicalDilemmas), 0.9, 0.4).await;
+    let advanced_ethicist = assess(&topology, AgentLevel::L15, Some(QuestionCategory::EthicalDilemmas), 0.8, 0.4).await;
+    let generalist = assess(&topology, AgentLevel::L9, None, 0.0, 0.55).await;
+
+    let logic = topology.find_specialists(QuestionCategory::LogicalReasoning, 2).await;
+    assert_eq!(logic.len(), 2);
+    assert_eq!(logic[0].agent_id, logician);
+    assert!((logic[0].score - 0.9).abs() < 0.06, "{:?}", logic[0]);
+    assert_eq!(topology.find_specialists(QuestionCategory::PatternRecognition, 1).await[0].agent_id, spotter);
+
+    // The advanced ethicist scores lower but sits two layers up
+    let ethics = topology.find_specialists(QuestionCategory::EthicalDilemmas, 5).await;
+    let ranked: Vec<Uuid> = ethics.iter().map(|specialist| specialist.agent_id).collect();
+    assert_eq!(ranked[..2], [advanced_ethicist, basic_ethicist]);
+    assert!(ethics[0].score < ethics[1].score);
+    assert_eq!(ranked[2], generalist);
+    assert!(topology.find_specialists(QuestionCategory::MetaCognition, 3).await.is_empty());
+
+    let sole = topology.sole_specialists(0.7).await;
+    assert!(sole.contains(&logician) && sole.contains(&spotter));
+    assert!(!sole.contains(&basic_ethicist) && !sole.contains(&advanced_ethicist) && !sole.contains(&generalist));
+
+    // A peer review of a question counts towards its category
+    let engine = EvaluationEngine::new();
+    let question = AssessmentQuestion {
+        id: Uuid::new_v4(),
+        category: QuestionCategory::SystemsThinking,
+        difficulty: AgentLevel::L8,
+        content: "Design a rate limiter".to_string(),
+        time_limit: None,
+    };
+    let response = AssessmentResponse {
+        question_id: question.id,
+        answer: "Token bucket per client".to_string(),
+        time_taken: Duration::from_secs(20),
+        confidence: 0.9,
+    };
+    let review = engine.evaluate_response(logician, generalist, &question, &response);
+    assert_eq!(review.category, Some(QuestionCategory::SystemsThinking));
+    let specialization = topology.record_peer_review(&review).await.unwrap();
+    assert_eq!(specialization.score(QuestionCategory::SystemsThinking), Some(review.scores.overall()));
+    assert!(topology.record_peer_review(&MutualEvaluation { category: None, ..review.clone() }).await.is_none());
+
+    let stats = topology.get_network_stats().await;
+    let ethics = &stats.specialization_distribution[&QuestionCategory::EthicalDilemmas];
+    assert_eq!((ethics.agents, ethics.specialists), (5, 2));
+    assert!((ethics.top_score - 0.9).abs() < 0.06);
+    assert_eq!(stats.specialization_distribution[&QuestionCategory::SystemsThinking].agents, 1);
+
+    // Specializations survive a restart
+    let expected = topology.specialization(spotter).await.unwrap();
+    drop(topology);
+    let restored = NetworkTopology::restore(Arc::new(FileTopologyStore::open(dir.path()).unwrap()), None).await.unwrap();
+    assert_eq!(restored.specialization(spotter).await, Some(expected));
+}
+
+fn config() -> DropoutConfig {
+    DropoutConfig {
+        dropout_threshold: 0.25,
+        evaluation_interval: Duration::ZERO,
+        minimum_agent_count: 3,
+        grace_evaluations: 10,
+        success_threshold: 0.7,
+        confidence_z: 1.96,
+        min_agents: 3,
+        max_agents: 100,
+        specialist_threshold: 0.7,
+        specialist_protection: 0.5,
+    }
+}
+
+async fn register(controller: &DropoutOrchestrator, agents: &[(Uuid, f32)]) {
+    for &(id, performance) in agents {
+        let agent = ScoredAgent { id, level: AgentLevel::L8, performance };
+        controller.register_agent(Box::new(agent)).await.unwrap();
+        for _ in 0..200 {
+            controller.record_evaluation(id, performance).await.unwrap();
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_sole_specialist_protected_from_dropout() {
+    let topology = Arc::new(NetworkTopology::new());
+    let ethicist = assess(&topology, AgentLevel::L8, Some(QuestionCategory::EthicalDilemmas), 0.9, 0.3).await;
+    let mut generalists = Vec::new();
+    for _ in 0..3 {
+        generalists.push(assess(&topology, AgentLevel::L8, None, 0.0, 0.6).await);
+    }
+    let agents = [(ethicist, 0.5), (generalists[0], 0.6), (generalists[1], 0.65), (generalists[2], 0.7)];
+
+    // Without learned specializations the ethicist ranks last and goes
+    let unprotected = DropoutOrchestrator::with_config(config());
+    register(&unprotected, &agents).await;
+    assert_eq!(unprotected.health_check_cycle().await.unwrap().dropped_agents, vec![ethicist]);
+
+    let protected = DropoutOrchestrator::with_config(config()).with_topology(topology.clone());
+    register(&protected, &agents).await;
+    assert_eq!(protected.health_check_cycle().await.unwrap().dropped_agents, vec![generalists[0]]);
+
+    let controller = DropoutOrchestrator::new(0, Duration::from_secs(300), 0.7).with_topology(topology.clone());
+    let profile = |id| AgentProfile::new(id, AgentLevel::L8);
+    assert!(!controller.should_dropout(&profile(ethicist), 0.55).await);
+    assert!(controller.should_dropout(&profile(generalists[1]), 0.55).await);
+    assert!(controller.should_dropout(&profile(ethicist), 0.4).await);
+
+    // A second strong ethicist ends the protection
+    assess(&topology, AgentLevel::L12, Some(QuestionCategory::EthicalDilemmas), 0.85, 0.3).await;
+    assert!(controller.should_dropout(&profile(ethicist), 0.55).await);
+}
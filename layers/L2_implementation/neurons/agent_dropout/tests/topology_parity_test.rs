@@ -0,0 +1,268 @@
+//! Adjacency-backed topology against the petgraph implementation it replaced
+
+use agent_dropout::network::{AgentNode, ConnectionEdge};
+use agent_dropout::specialization::{SMOOTHING, SPECIALIST_SCORE};
+use agent_dropout::{
+    AgentLevel, AgentProfile, CategoryDistribution, EvaluationResult, NetworkStats, NetworkTopology,
+    QuestionCategory,
+};
+use chrono::Utc;
+use petgraph::graph::{DiGraph, NodeIndex};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use uuid::Uuid;
+
+const CATEGORIES: [QuestionCategory; 6] = [
+    QuestionCategory::LogicalReasoning,
+    QuestionCategory::PatternRecognition,
+    QuestionCategory::CreativeProblemSolving,
+    QuestionCategory::SystemsThinking,
+    QuestionCategory::MetaCognition,
+    QuestionCategory::EthicalDilemmas,
+];
+
+/// The topology as it was kept before: a petgraph graph and an index of
+/// it, changed the way `NetworkTopology` used to change them
+#[derive(Default)]
+struct Reference {
+    graph: DiGraph<AgentNode, ConnectionEdge>,
+    indices: HashMap<Uuid, NodeIndex>,
+}
+
+impl Reference {
+    fn place(&mut self, profile: &AgentProfile) {
+        let node = AgentNode {
+            id: profile.id,
+            level: profile.capability_level,
+            layer: profile.capability_level.layer(),
+            connections_count: 0,
+            specialization: profile.specialization.clone(),
+        };
+        self.indices.insert(profile.id, self.graph.add_node(node));
+    }
+
+    fn connect(&mut self, agent1: Uuid, agent2: Uuid, strength: f32) {
+        if let (Some(&idx1), Some(&idx2)) = (self.indices.get(&agent1), self.indices.get(&agent2)) {
+            let now = Utc::now();
+            let edge = ConnectionEdge { strength, interaction_count: 0, last_interaction: now, updated_at: now };
+            self.graph.add_edge(idx1, idx2, edge.clone());
+            self.graph.add_edge(idx2, idx1, edge);
+            self.graph[idx1].connections_count += 1;
+            self.graph[idx2].connections_count += 1;
+        }
+    }
+
+    fn update(&mut self, agent1: Uuid, agent2: Uuid, success: bool) {
+        if let (Some(&idx1), Some(&idx2)) = (self.indices.get(&agent1), self.indices.get(&agent2)) {
+            if let Some(edge) = self.graph.find_edge(idx1, idx2) {
+                let edge = &mut self.graph[edge];
+                edge.interaction_count += 1;
+                edge.strength = if success { (edge.strength * 1.1).min(1.0) } else { (edge.strength * 0.9).max(0.1) };
+            }
+        }
+    }
+
+    fn are_connected(&self, agent1: Uuid, agent2: Uuid) -> bool {
+        match (self.indices.get(&agent1), self.indices.get(&agent2)) {
+            (Some(&idx1), Some(&idx2)) => {
+                self.graph.find_edge(idx1, idx2).is_some() || self.graph.find_edge(idx2, idx1).is_some()
+            }
+            _ => false,
+        }
+    }
+
+    fn move_agent(&mut self, agent: Uuid, level: AgentLevel) {
+        let node = &mut self.graph[self.indices[&agent]];
+        node.level = level;
+        node.layer = level.layer();
+    }
+
+    fn observe(&mut self, agent: Uuid, category: QuestionCategory, score: f32) {
+        self.graph[self.indices[&agent]].specialization.observe(category, score, SMOOTHING);
+    }
+
+    fn remove(&mut self, agent: Uuid) {
+        if let Some(idx) = self.indices.remove(&agent) {
+            self.graph.remove_node(idx);
+            if let Some(moved) = self.graph.node_weight(idx) {
+                self.indices.insert(moved.id, idx);
+            }
+        }
+    }
+
+    fn ids(&self) -> Vec<Uuid> {
+        self.graph.node_weights().map(|node| node.id).collect()
+    }
+
+    /// Statistics recomputed from scratch, as `get_network_stats` did
+    fn stats(&self) -> NetworkStats {
+        let graph = &self.graph;
+        let total_agents = graph.node_count();
+        let total_connections = graph.edge_count();
+
+        let mut layer_distribution = HashMap::new();
+        for node in graph.node_weights() {
+            *layer_distribution.entry(node.layer).or_insert(0) += 1;
+        }
+
+        let neighbors: Vec<BTreeSet<usize>> = graph.node_indices()
+            .map(|idx| graph.neighbors_undirected(idx).filter(|n| *n != idx).map(|n| n.index()).collect())
+            .collect();
+        let mut degree_distribution = BTreeMap::new();
+        let mut clustering_sum = 0.0f64;
+        for adjacent in &neighbors {
+            *degree_distribution.entry(adjacent.len()).or_insert(0) += 1;
+            let degree = adjacent.len();
+            if degree >= 2 {
+                let links = adjacent.iter()
+                    .map(|&u| adjacent.range(u + 1..).filter(|v| neighbors[u].contains(v)).count())
+                    .sum::<usize>();
+                clustering_sum += (2 * links) as f64 / (degree * (degree - 1)) as f64;
+            }
+        }
+
+        let mut category_scores: HashMap<QuestionCategory, Vec<f32>> = HashMap::new();
+        for node in graph.node_weights() {
+            for (category, skill) in node.specialization.iter() {
+                category_scores.entry(category).or_default().push(skill.score);
+            }
+        }
+        let specialization_distribution = category_scores
+            .into_iter()
+            .map(|(category, scores)| {
+                let distribution = CategoryDistribution {
+                    agents: scores.len(),
+                    specialists: scores.iter().filter(|&&score| score >= SPECIALIST_SCORE).count(),
+                    mean_score: scores.iter().sum::<f32>() / scores.len() as f32,
+                    top_score: scores.iter().copied().fold(0.0, f32::max),
+                };
+                (category, distribution)
+            })
+            .collect();
+
+        NetworkStats {
+            total_agents,
+            total_connections,
+            average_connectivity: if total_agents > 0 {
+                (total_connections * 2) as f32 / total_agents as f32
+            } else {
+                0.0
+            },
+            layer_distribution,
+            degree_distribution,
+            clustering_coefficient: if total_agents > 0 {
+                (clustering_sum / total_agents as f64) as f32
+            } else {
+                0.0
+            },
+            specialization_distribution,
+        }
+    }
+}
+
+fn random_level(rng: &mut StdRng) -> AgentLevel {
+    AgentLevel::from_value(rng.gen_range(1..=20)).unwrap()
+}
+
+/// Agents and connections in storage order, without timestamps
+async fn assert_same_topology(topology: &NetworkTopology, reference: &Reference) {
+    let snapshot = topology.snapshot().await;
+    let agents: Vec<_> = snapshot.agents.iter()
+        .map(|node| serde_json::to_value(node).unwrap())
+        .collect();
+    let expected: Vec<_> = reference.graph.node_weights()
+        .map(|node| serde_json::to_value(node).unwrap())
+        .collect();
+    assert_eq!(agents, expected);
+
+    let connections: Vec<_> = snapshot.connections.iter()
+        .map(|c| (c.from, c.to, c.edge.strength, c.edge.interaction_count))
+        .collect();
+    let expected: Vec<_> = reference.graph.edge_indices()
+        .map(|edge| {
+            let (from, to) = reference.graph.edge_endpoints(edge).unwrap();
+            let weight = &reference.graph[edge];
+            (reference.graph[from].id, reference.graph[to].id, weight.strength, weight.interaction_count)
+        })
+        .collect();
+    assert_eq!(connections, expected);
+}
+
+#[tokio::test]
+async fn test_stats_match_recomputation_on_small_networks() {
+    for seed in 0..8 {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let topology = NetworkTopology::new();
+        let mut reference = Reference::default();
+
+        for step in 0..400 {
+            let ids = reference.ids();
+            let pick = |rng: &mut StdRng| ids.choose(rng).copied();
+            match rng.gen_range(0..100) {
+                // Placement connects to the layer heads
+                0..=29 => {
+                    let profile = AgentProfile::new(Uuid::from_u128(rng.gen()), random_level(&mut rng));
+                    let position = topology.place_agent(&profile).await;
+                    reference.place(&profile);
+                    for target in &position.initial_connections {
+                        reference.connect(profile.id, *target, 0.5);
+                    }
+                }
+                // Repeated and self connections make parallel edges and loops
+                30..=44 => {
+                    if let (Some(a), Some(b)) = (pick(&mut rng), pick(&mut rng)) {
+                        let strength = rng.gen_range(0.1..1.0);
+                        topology.connect_agents(a, b, strength).await;
+                        reference.connect(a, b, strength);
+                    }
+                }
+                45..=64 => {
+                    let edge = reference.graph.edge_indices().collect::<Vec<_>>().choose(&mut rng).copied();
+                    if let Some(edge) = edge {
+                        let (from, to) = reference.graph.edge_endpoints(edge).unwrap();
+                        let (a, b) = (reference.graph[from].id, reference.graph[to].id);
+                        let success = rng.gen_bool(0.5);
+                        topology.update_connection(a, b, success).await;
+                        reference.update(a, b, success);
+                    }
+                }
+                65..=74 => {
+                    if let Some(agent) = pick(&mut rng) {
+                        let level = random_level(&mut rng);
+                        assert!(topology.move_agent(agent, level).await);
+                        reference.move_agent(agent, level);
+                    }
+                }
+                75..=84 => {
+                    if let Some(agent) = pick(&mut rng) {
+                        let category = *CATEGORIES.choose(&mut rng).unwrap();
+                        let score = rng.gen_range(0.0..1.0);
+                        let result = EvaluationResult {
+                            overall_score: score,
+                            level_estimate: AgentLevel::L5,
+                            category_scores: HashMap::from([(category, score)]),
+                            time_efficiency: 1.0,
+                            consistency_score: 1.0,
+                        };
+                        topology.record_evaluation(agent, &result).await.unwrap();
+                        reference.observe(agent, category, score);
+                    }
+                }
+                _ => {
+                    if let Some(agent) = pick(&mut rng) {
+                        topology.remove_agent(agent).await;
+                        reference.remove(agent);
+                    }
+                }
+            }
+
+            assert_eq!(topology.get_network_stats().await, reference.stats(), "seed {} step {}", seed, step);
+            if let (Some(a), Some(b)) = (pick(&mut rng), pick(&mut rng)) {
+                assert_eq!(topology.are_connected(a, b).await, reference.are_connected(a, b));
+            }
+        }
+        assert_same_topology(&topology, &reference).await;
+    }
+}
@@ -0,0 +1,506 @@
+//! API key management
+//!
+//! Keys are scoped to a subset of their owner's permissions and may be bound
+//! to an organization. Only a SHA-256 hash of the secret is stored; the
+//! secret itself is returned once, when a key is created or rotated. Rotating
+//! keeps the previous secret valid for an overlap window so clients can roll
+//! over without downtime. Every validation reads the key's row, so revocation
+//! applies to the next request.
+
+use chrono::{Duration, Utc};
+use dashmap::DashMap;
+use rand::{distributions::Alphanumeric, Rng};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::FromRow;
+use uuid::Uuid;
+use crate::auth::types::{AuthError, AuthResult, Permissions};
+use crate::sqlite::SqlitePools;
+
+/// Prefix every issued key starts with
+pub const KEY_PREFIX: &str = "hal9_";
+
+/// Length of the identifying prefix shown in key listings
+const DISPLAY_PREFIX_LEN: usize = 12;
+
+/// Random characters in a key's secret
+const SECRET_LEN: usize = 40;
+
+/// How long a rotated-out secret stays valid unless the request says otherwise
+pub const DEFAULT_ROTATION_OVERLAP_MINUTES: i64 = 60;
+
+/// Stored API key
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct ApiKey {
+    pub id: String,
+    pub user_id: String,
+    pub name: String,
+    /// Leading characters of the current secret, enough to recognise it
+    pub prefix: String,
+    #[serde(skip_serializing)]
+    pub key_hash: String,
+    /// Hash of the secret replaced by the last rotation
+    #[serde(skip_serializing)]
+    pub previous_hash: Option<String>,
+    /// When the previous secret stops being accepted
+    pub previous_expires_at: Option<i64>,
+    /// Scopes as serialized [`Permissions`]
+    pub permissions: String,
+    pub org_id: Option<String>,
+    pub expires_at: Option<i64>,
+    pub revoked: bool,
+    pub created_at: i64,
+    pub rotated_at: Option<i64>,
+    pub last_used_at: Option<i64>,
+    pub request_count: i64,
+    pub rate_limit_per_minute: Option<i64>,
+    pub token_budget: Option<i64>,
+    pub tokens_used: i64,
+}
+
+impl ApiKey {
+    /// Scopes granted to the key
+    pub fn scopes(&self) -> Permissions {
+        serde_json::from_str(&self.permissions).unwrap_or_default()
+    }
+}
+
+/// Limits attached to a single key
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ApiKeyLimits {
+    /// Requests the key may make per minute
+    pub rate_limit_per_minute: Option<u32>,
+    /// Claude tokens chains started with the key may spend in total
+    pub token_budget: Option<u64>,
+}
+
+/// API key creation request
+#[derive(Debug, Default, Deserialize)]
+pub struct CreateApiKeyRequest {
+    pub name: String,
+    /// Scopes; must be a subset of the creator's own permissions
+    pub permissions: Permissions,
+    pub expires_in_days: Option<i64>,
+    /// Organization the key acts for
+    #[serde(default)]
+    pub org_id: Option<String>,
+    #[serde(default)]
+    pub limits: ApiKeyLimits,
+}
+
+/// API key rotation request
+#[derive(Debug, Default, Deserialize)]
+pub struct RotateApiKeyRequest {
+    /// Minutes the replaced secret stays valid, defaults to
+    /// [`DEFAULT_ROTATION_OVERLAP_MINUTES`]
+    pub overlap_minutes: Option<i64>,
+}
+
+/// Newly issued secret; the only time it is ever returned
+#[derive(Debug, Clone, Serialize)]
+pub struct ApiKeyResponse {
+    pub id: String,
+    pub name: String,
+    pub key: String,
+    pub prefix: String,
+    pub permissions: Permissions,
+    pub org_id: Option<String>,
+    pub expires_at: Option<i64>,
+    pub created_at: i64,
+    /// When the secret this one replaced stops being accepted
+    pub previous_expires_at: Option<i64>,
+}
+
+/// Key metadata as listed to its owner, without any secret
+#[derive(Debug, Clone, Serialize)]
+pub struct ApiKeyInfo {
+    pub id: String,
+    pub name: String,
+    pub prefix: String,
+    pub permissions: Permissions,
+    pub org_id: Option<String>,
+    pub expires_at: Option<i64>,
+    pub revoked: bool,
+    pub created_at: i64,
+    pub rotated_at: Option<i64>,
+    pub previous_expires_at: Option<i64>,
+    pub last_used_at: Option<i64>,
+    pub request_count: i64,
+    pub rate_limit_per_minute: Option<i64>,
+    pub token_budget: Option<i64>,
+    pub tokens_used: i64,
+}
+
+impl From<ApiKey> for ApiKeyInfo {
+    fn from(key: ApiKey) -> Self {
+        Self {
+            permissions: key.scopes(),
+            id: key.id,
+            name: key.name,
+            prefix: key.prefix,
+            org_id: key.org_id,
+            expires_at: key.expires_at,
+            revoked: key.revoked,
+            created_at: key.created_at,
+            rotated_at: key.rotated_at,
+            previous_expires_at: key.previous_expires_at,
+            last_used_at: key.last_used_at,
+            request_count: key.request_count,
+            rate_limit_per_minute: key.rate_limit_per_minute,
+            token_budget: key.token_budget,
+            tokens_used: key.tokens_used,
+        }
+    }
+}
+
+/// Generate a new secret and its display prefix
+fn generate_secret() -> (String, String) {
+    let random: String = rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(SECRET_LEN)
+        .map(char::from)
+        .collect();
+    let key = format!("{}{}", KEY_PREFIX, random);
+    let prefix = key[..DISPLAY_PREFIX_LEN].to_string();
+    (key, prefix)
+}
+
+/// Hash of a secret as stored. Secrets are long and random, so a fast hash
+/// can be looked up directly without exposing them to offline guessing.
+fn hash_secret(key: &str) -> String {
+    hex::encode(Sha256::digest(key.as_bytes()))
+}
+
+/// API key manager for database operations
+pub struct ApiKeyManager {
+    pools: SqlitePools,
+    /// Requests per key in the current minute, as `(minute, count)`
+    windows: DashMap<String, (i64, u32)>,
+}
+
+impl ApiKeyManager {
+    /// Create an API key manager on a single pool or on tuned reader and writer pools
+    pub fn new(pools: impl Into<SqlitePools>) -> Self {
+        Self {
+            pools: pools.into(),
+            windows: DashMap::new(),
+        }
+    }
+
+    /// Initialize API key tables
+    pub async fn initialize(&self) -> AuthResult<()> {
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS api_keys (
+                id TEXT PRIMARY KEY,
+                user_id TEXT NOT NULL,
+                name TEXT NOT NULL,
+                prefix TEXT NOT NULL,
+                key_hash TEXT UNIQUE NOT NULL,
+                previous_hash TEXT,
+                previous_expires_at INTEGER,
+                permissions TEXT NOT NULL,
+                org_id TEXT,
+                expires_at INTEGER,
+                revoked BOOLEAN NOT NULL DEFAULT FALSE,
+                created_at INTEGER NOT NULL,
+                rotated_at INTEGER,
+                last_used_at INTEGER,
+                request_count INTEGER NOT NULL DEFAULT 0,
+                rate_limit_per_minute INTEGER,
+                token_budget INTEGER,
+                tokens_used INTEGER NOT NULL DEFAULT 0
+            )
+            "#
+        )
+        .execute(self.pools.writer())
+        .await
+        .map_err(|e| AuthError::DatabaseError(e.to_string()))?;
+
+        // Create indexes
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_api_keys_user_id ON api_keys(user_id)")
+            .execute(self.pools.writer())
+            .await
+            .map_err(|e| AuthError::DatabaseError(e.to_string()))?;
+
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_api_keys_previous_hash ON api_keys(previous_hash)")
+            .execute(self.pools.writer())
+            .await
+            .map_err(|e| AuthError::DatabaseError(e.to_string()))?;
+
+        Ok(())
+    }
+
+    /// Create a new API key for `user_id`
+    pub async fn create_api_key(&self, user_id: &str, request: CreateApiKeyRequest) -> AuthResult<ApiKeyResponse> {
+        if request.name.is_empty() {
+            return Err(AuthError::ValidationError("API key name is required".to_string()));
+        }
+
+        if matches!(request.expires_in_days, Some(days) if days <= 0) {
+            return Err(AuthError::ValidationError("expires_in_days must be positive".to_string()));
+        }
+
+        let permissions = serde_json::to_string(&request.permissions)
+            .map_err(|e| AuthError::ValidationError(e.to_string()))?;
+        let (key, prefix) = generate_secret();
+        let now = Utc::now();
+
+        let api_key = ApiKey {
+            id: Uuid::new_v4().to_string(),
+            user_id: user_id.to_string(),
+            name: request.name,
+            prefix,
+            key_hash: hash_secret(&key),
+            previous_hash: None,
+            previous_expires_at: None,
+            permissions,
+            org_id: request.org_id,
+            expires_at: request.expires_in_days.map(|days| (now + Duration::days(days)).timestamp()),
+            revoked: false,
+            created_at: now.timestamp(),
+            rotated_at: None,
+            last_used_at: None,
+            request_count: 0,
+            rate_limit_per_minute: request.limits.rate_limit_per_minute.map(i64::from),
+            token_budget: request.limits.token_budget.map(|budget| budget as i64),
+            tokens_used: 0,
+        };
+
+        // Insert key
+        let row = &api_key;
+        self.pools.write(|pool| async move {
+            sqlx::query(
+                r#"
+                INSERT INTO api_keys (id, user_id, name, prefix, key_hash, permissions, org_id, expires_at,
+                                      created_at, rate_limit_per_minute, token_budget)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+                "#
+            )
+            .bind(&row.id)
+            .bind(&row.user_id)
+            .bind(&row.name)
+            .bind(&row.prefix)
+            .bind(&row.key_hash)
+            .bind(&row.permissions)
+            .bind(&row.org_id)
+            .bind(row.expires_at)
+            .bind(row.created_at)
+            .bind(row.rate_limit_per_minute)
+            .bind(row.token_budget)
+            .execute(&pool)
+            .await
+        })
+        .await
+        .map_err(|e| AuthError::DatabaseError(e.to_string()))?;
+
+        Ok(ApiKeyResponse {
+            permissions: api_key.scopes(),
+            id: api_key.id,
+            name: api_key.name,
+            key,
+            prefix: api_key.prefix,
+            org_id: api_key.org_id,
+            expires_at: api_key.expires_at,
+            created_at: api_key.created_at,
+            previous_expires_at: None,
+        })
+    }
+
+    /// Get one of a user's keys
+    pub async fn get_api_key(&self, user_id: &str, key_id: &str) -> AuthResult<ApiKey> {
+        sqlx::query_as::<_, ApiKey>(
+            "SELECT * FROM api_keys WHERE id = $1 AND user_id = $2"
+        )
+        .bind(key_id)
+        .bind(user_id)
+        .fetch_one(self.pools.reader())
+        .await
+        .map_err(|_| AuthError::ApiKeyNotFound)
+    }
+
+    /// List a user's keys, newest first
+    pub async fn list_user_api_keys(&self, user_id: &str) -> AuthResult<Vec<ApiKey>> {
+        sqlx::query_as::<_, ApiKey>(
+            "SELECT * FROM api_keys WHERE user_id = $1 ORDER BY created_at DESC"
+        )
+        .bind(user_id)
+        .fetch_all(self.pools.reader())
+        .await
+        .map_err(|e| AuthError::DatabaseError(e.to_string()))
+    }
+
+    /// Issue a new secret for a key. The current secret stays valid for
+    /// `overlap`; a zero overlap retires it at once.
+    pub async fn rotate_api_key(&self, user_id: &str, key_id: &str, overlap: Duration) -> AuthResult<ApiKeyResponse> {
+        if overlap < Duration::zero() {
+            return Err(AuthError::ValidationError("Rotation overlap cannot be negative".to_string()));
+        }
+
+        let api_key = self.get_api_key(user_id, key_id).await?;
+        if api_key.revoked {
+            return Err(AuthError::ApiKeyRevoked);
+        }
+
+        let (key, prefix) = generate_secret();
+        let key_hash = hash_secret(&key);
+        let now = Utc::now();
+        let previous_expires_at = (now + overlap).timestamp();
+
+        let (key_hash, prefix) = (&key_hash, &prefix);
+        let previous_hash = &api_key.key_hash;
+        self.pools.write(|pool| async move {
+            sqlx::query(
+                r#"
+                UPDATE api_keys
+                SET key_hash = $3, prefix = $4, previous_hash = $5, previous_expires_at = $6, rotated_at = $7
+                WHERE id = $1 AND user_id = $2
+                "#
+            )
+            .bind(key_id)
+            .bind(user_id)
+            .bind(key_hash)
+            .bind(prefix)
+            .bind(previous_hash)
+            .bind(previous_expires_at)
+            .bind(now.timestamp())
+            .execute(&pool)
+            .await
+        })
+        .await
+        .map_err(|e| AuthError::DatabaseError(e.to_string()))?;
+
+        Ok(ApiKeyResponse {
+            permissions: api_key.scopes(),
+            id: api_key.id,
+            name: api_key.name,
+            key,
+            prefix: prefix.clone(),
+            org_id: api_key.org_id,
+            expires_at: api_key.expires_at,
+            created_at: api_key.created_at,
+            previous_expires_at: Some(previous_expires_at),
+        })
+    }
+
+    /// Revoke a key; both its current and any overlapping secret stop working
+    pub async fn revoke_api_key(&self, user_id: &str, key_id: &str) -> AuthResult<()> {
+        let result = self.pools
+            .write(|pool| async move {
+                sqlx::query("UPDATE api_keys SET revoked = TRUE WHERE id = $1 AND user_id = $2")
+                    .bind(key_id)
+                    .bind(user_id)
+                    .execute(&pool)
+                    .await
+            })
+            .await
+            .map_err(|e| AuthError::DatabaseError(e.to_string()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(AuthError::ApiKeyNotFound);
+        }
+
+        Ok(())
+    }
+
+    /// Delete a key
+    pub async fn delete_api_key(&self, user_id: &str, key_id: &str) -> AuthResult<()> {
+        let result = self.pools
+            .write(|pool| async move {
+                sqlx::query("DELETE FROM api_keys WHERE id = $1 AND user_id = $2")
+                    .bind(key_id)
+                    .bind(user_id)
+                    .execute(&pool)
+                    .await
+            })
+            .await
+            .map_err(|e| AuthError::DatabaseError(e.to_string()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(AuthError::ApiKeyNotFound);
+        }
+
+        self.windows.remove(key_id);
+        Ok(())
+    }
+
+    /// Validate a presented secret and record the request against its key.
+    ///
+    /// Accepts the key's current secret, or its previous one while the
+    /// rotation overlap lasts. Fails for revoked or expired keys, keys whose
+    /// token budget is spent, and keys over their per-minute request limit.
+    pub async fn validate_api_key(&self, key: &str) -> AuthResult<(ApiKey, Permissions)> {
+        let key_hash = hash_secret(key);
+        let now = Utc::now().timestamp();
+
+        let api_key = sqlx::query_as::<_, ApiKey>(
+            r#"
+            SELECT * FROM api_keys
+            WHERE key_hash = $1 OR (previous_hash = $1 AND previous_expires_at > $2)
+            "#
+        )
+        .bind(&key_hash)
+        .bind(now)
+        .fetch_optional(self.pools.reader())
+        .await
+        .map_err(|e| AuthError::DatabaseError(e.to_string()))?
+        .ok_or(AuthError::ApiKeyNotFound)?;
+
+        if api_key.revoked {
+            return Err(AuthError::ApiKeyRevoked);
+        }
+
+        if matches!(api_key.expires_at, Some(expires_at) if expires_at <= now) {
+            return Err(AuthError::ApiKeyExpired);
+        }
+
+        if matches!(api_key.token_budget, Some(budget) if api_key.tokens_used >= budget) {
+            return Err(AuthError::BudgetExhausted);
+        }
+
+        if let Some(limit) = api_key.rate_limit_per_minute {
+            let minute = now / 60;
+            let mut window = self.windows.entry(api_key.id.clone()).or_insert((minute, 0));
+            if window.0 != minute {
+                *window = (minute, 0);
+            }
+            if i64::from(window.1) >= limit {
+                return Err(AuthError::RateLimitExceeded);
+            }
+            window.1 += 1;
+        }
+
+        // Record usage for audits
+        let key_id = &api_key.id;
+        self.pools.write(|pool| async move {
+            sqlx::query(
+                "UPDATE api_keys SET last_used_at = $2, request_count = request_count + 1 WHERE id = $1"
+            )
+            .bind(key_id)
+            .bind(now)
+            .execute(&pool)
+            .await
+        })
+        .await
+        .map_err(|e| AuthError::DatabaseError(e.to_string()))?;
+
+        let permissions = api_key.scopes();
+        Ok((api_key, permissions))
+    }
+
+    /// Charge tokens spent by a chain started with the key to its budget
+    pub async fn record_token_usage(&self, key_id: &str, tokens: u64) -> AuthResult<()> {
+        self.pools
+            .write(|pool| async move {
+                sqlx::query("UPDATE api_keys SET tokens_used = tokens_used + $2 WHERE id = $1")
+                    .bind(key_id)
+                    .bind(tokens as i64)
+                    .execute(&pool)
+                    .await
+            })
+            .await
+            .map_err(|e| AuthError::DatabaseError(e.to_string()))?;
+
+        Ok(())
+    }
+}
@@ -7,5 +7,5 @@ pub mod types;
 
 pub use user::{User, UserManager, UserRole, CreateUserRequest, UpdateUserRequest};
 pub use jwt::{JwtClaims, JwtManager, TokenPair};
-pub use api_key::{ApiKey, ApiKeyManager, CreateApiKeyRequest, ApiKeyResponse, ApiKeyInfo};
+pub use api_key::{ApiKey, ApiKeyManager, CreateApiKeyRequest, RotateApiKeyRequest, ApiKeyLimits, ApiKeyResponse, ApiKeyInfo, DEFAULT_ROTATION_OVERLAP_MINUTES};
 pub use types::{AuthError, AuthResult, Permissions, Permission};
\ No newline at end of file
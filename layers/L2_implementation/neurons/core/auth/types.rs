@@ -33,6 +33,15 @@ pub enum AuthError {
     #[error("API key expired")]
     ApiKeyExpired,
     
+    #[error("API key revoked")]
+    ApiKeyRevoked,
+    
+    #[error("API key rate limit exceeded")]
+    RateLimitExceeded,
+    
+    #[error("API key token budget exhausted")]
+    BudgetExhausted,
+    
     #[error("Password hash error: {0}")]
     PasswordHashError(String),
     
@@ -72,6 +81,9 @@ pub enum Permission {
     // Cost permissions
     ViewCosts,
     SetCostLimits,
+    
+    // Organization permissions; scoped to the caller's organization
+    ViewOrgUsage,
 }
 
 /// Permission set
@@ -112,6 +124,22 @@ impl Permissions {
     pub fn has_any(&self, perms: &[Permission]) -> bool {
         perms.iter().any(|p| self.has(p))
     }
+    
+    pub fn iter(&self) -> impl Iterator<Item = &Permission> {
+        self.permissions.iter()
+    }
+    
+    /// Whether every permission in `other` is also in this set
+    pub fn contains_all(&self, other: &Permissions) -> bool {
+        other.iter().all(|p| self.has(p))
+    }
+    
+    /// Permissions held by both sets
+    pub fn intersect(&self, other: &Permissions) -> Permissions {
+        Self {
+            permissions: self.permissions.intersection(&other.permissions).cloned().collect(),
+        }
+    }
 }
 
 impl Default for Permissions {
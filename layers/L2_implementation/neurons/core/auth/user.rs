@@ -4,8 +4,9 @@ use chrono::Utc;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 use bcrypt::{hash, verify, DEFAULT_COST};
-use sqlx::{SqlitePool, FromRow};
+use sqlx::FromRow;
 use crate::auth::types::{AuthError, AuthResult, Permissions, Permission};
+use crate::sqlite::SqlitePools;
 
 /// User roles
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
@@ -45,6 +46,7 @@ impl UserRole {
                 Permission::SystemAdmin,
                 Permission::ViewCosts,
                 Permission::SetCostLimits,
+                Permission::ViewOrgUsage,
             ]),
             UserRole::User => Permissions::with_permissions(vec![
                 Permission::CreateNeuron,
@@ -98,12 +100,13 @@ pub struct UpdateUserRequest {
 
 /// User manager for database operations
 pub struct UserManager {
-    pool: SqlitePool,
+    pools: SqlitePools,
 }
 
 impl UserManager {
-    pub fn new(pool: SqlitePool) -> Self {
-        Self { pool }
+    /// Create a user manager on a single pool or on tuned reader and writer pools
+    pub fn new(pools: impl Into<SqlitePools>) -> Self {
+        Self { pools: pools.into() }
     }
     
     /// Initialize user tables
@@ -122,18 +125,18 @@ impl UserManager {
             )
             "#
         )
-        .execute(&self.pool)
+        .execute(self.pools.writer())
         .await
         .map_err(|e| AuthError::DatabaseError(e.to_string()))?;
         
         // Create indexes
         sqlx::query("CREATE INDEX IF NOT EXISTS idx_users_username ON users(username)")
-            .execute(&self.pool)
+            .execute(self.pools.writer())
             .await
             .map_err(|e| AuthError::DatabaseError(e.to_string()))?;
             
         sqlx::query("CREATE INDEX IF NOT EXISTS idx_users_email ON users(email)")
-            .execute(&self.pool)
+            .execute(self.pools.writer())
             .await
             .map_err(|e| AuthError::DatabaseError(e.to_string()))?;
         
@@ -167,21 +170,25 @@ impl UserManager {
         };
         
         // Insert user
-        sqlx::query(
-            r#"
-            INSERT INTO users (id, username, email, password_hash, role, created_at, updated_at, is_active)
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
-            "#
-        )
-        .bind(&user.id)
-        .bind(&user.username)
-        .bind(&user.email)
-        .bind(&user.password_hash)
-        .bind(&user.role)
-        .bind(user.created_at)
-        .bind(user.updated_at)
-        .bind(user.is_active)
-        .execute(&self.pool)
+        let row = &user;
+        self.pools.write(|pool| async move {
+            sqlx::query(
+                r#"
+                INSERT INTO users (id, username, email, password_hash, role, created_at, updated_at, is_active)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+                "#
+            )
+            .bind(&row.id)
+            .bind(&row.username)
+            .bind(&row.email)
+            .bind(&row.password_hash)
+            .bind(&row.role)
+            .bind(row.created_at)
+            .bind(row.updated_at)
+            .bind(row.is_active)
+            .execute(&pool)
+            .await
+        })
         .await
         .map_err(|e| {
             if e.to_string().contains("UNIQUE") {
@@ -217,7 +224,7 @@ impl UserManager {
             "SELECT * FROM users WHERE id = $1"
         )
         .bind(user_id)
-        .fetch_one(&self.pool)
+        .fetch_one(self.pools.reader())
         .await
         .map_err(|_| AuthError::UserNotFound)
     }
@@ -228,7 +235,7 @@ impl UserManager {
             "SELECT * FROM users WHERE username = $1"
         )
         .bind(username)
-        .fetch_one(&self.pool)
+        .fetch_one(self.pools.reader())
         .await
         .map_err(|_| AuthError::UserNotFound)
     }
@@ -251,19 +258,23 @@ impl UserManager {
         
         user.updated_at = Utc::now().timestamp();
         
-        sqlx::query(
-            r#"
-            UPDATE users 
-            SET email = $2, role = $3, is_active = $4, updated_at = $5
-            WHERE id = $1
-            "#
-        )
-        .bind(&user.id)
-        .bind(&user.email)
-        .bind(&user.role)
-        .bind(user.is_active)
-        .bind(user.updated_at)
-        .execute(&self.pool)
+        let row = &user;
+        self.pools.write(|pool| async move {
+            sqlx::query(
+                r#"
+                UPDATE users 
+                SET email = $2, role = $3, is_active = $4, updated_at = $5
+                WHERE id = $1
+                "#
+            )
+            .bind(&row.id)
+            .bind(&row.email)
+            .bind(&row.role)
+            .bind(row.is_active)
+            .bind(row.updated_at)
+            .execute(&pool)
+            .await
+        })
         .await
         .map_err(|e| AuthError::DatabaseError(e.to_string()))?;
         
@@ -272,9 +283,13 @@ impl UserManager {
     
     /// Delete user
     pub async fn delete_user(&self, user_id: &str) -> AuthResult<()> {
-        sqlx::query("DELETE FROM users WHERE id = $1")
-            .bind(user_id)
-            .execute(&self.pool)
+        self.pools
+            .write(|pool| async move {
+                sqlx::query("DELETE FROM users WHERE id = $1")
+                    .bind(user_id)
+                    .execute(&pool)
+                    .await
+            })
             .await
             .map_err(|e| AuthError::DatabaseError(e.to_string()))?;
         
@@ -284,7 +299,7 @@ impl UserManager {
     /// List all users
     pub async fn list_users(&self) -> AuthResult<Vec<User>> {
         sqlx::query_as::<_, User>("SELECT * FROM users ORDER BY created_at DESC")
-            .fetch_all(&self.pool)
+            .fetch_all(self.pools.reader())
             .await
             .map_err(|e| AuthError::DatabaseError(e.to_string()))
     }
@@ -3,6 +3,9 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
+use crate::metadata_schema::ValidationMode;
+use crate::secrets::{MasterKeyProvider, SecretString, SecretsCipher};
+
 /// Server configuration
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct ServerConfig {
@@ -36,419 +39,3629 @@ pub struct ServerConfig {
     /// Optional browser automation configuration
     #[serde(default)]
     pub browser: Option<BrowserConfig>,
+    
+    /// Per-user concurrency limits and fair scheduling
+    #[serde(default)]
+    pub limits: ChainLimitsConfig,
+    
+    /// Outbound webhook delivery
+    #[serde(default)]
+    pub webhooks: WebhookConfig,
+    
+    /// Load-based neuron cloning
+    #[serde(default)]
+    pub scaling: ScalingConfig,
+    
+    /// Response validation keyed by layer (e.g. "L2"). A neuron's
+    /// `settings.validation` overrides its layer's entry.
+    #[serde(default)]
+    pub validation: HashMap<String, ValidationConfig>,
+    
+    /// Dependency probes behind the health endpoints
+    #[serde(default)]
+    pub health: HealthConfig,
+    
+    /// Whether signal metadata outside the registered schema is rejected
+    /// or only logged
+    #[serde(default)]
+    pub metadata_validation: ValidationMode,
+    
+    /// Goal tracking and evaluation
+    #[serde(default)]
+    pub goals: GoalsConfig,
+    
+    /// Signing and anchoring of chain receipts
+    #[serde(default)]
+    pub receipts: ReceiptsConfig,
+    
+    /// Holding signals for layers paused by an operator
+    #[serde(default)]
+    pub layer_pause: LayerPauseConfig,
+    
+    /// Deterministic simulation for tests
+    #[serde(default)]
+    pub simulation: SimulationConfig,
+    
+    /// Layer compression measured from signal traffic
+    #[serde(default)]
+    pub compression: CompressionConfig,
+    
+    /// Replica recommendations and draining on termination
+    #[serde(default)]
+    pub autoscaling: AutoscalingConfig,
+    
+    /// Reformatting of final outputs that miss their requested format
+    #[serde(default)]
+    pub output_format: OutputFormatConfig,
+    
+    /// Pre-initialized instances of neuron types with a slow cold start
+    #[serde(default)]
+    pub warm_pool: WarmPoolConfig,
+    
+    /// Recovery playbooks keyed by layer (e.g. "L2"), run when a neuron
+    /// fails a signal. A neuron's `settings.recovery` overrides its layer's
+    /// entry.
+    #[serde(default)]
+    pub recovery: HashMap<String, RecoveryPlaybookConfig>,
+    
+    /// Local OpenAI-compatible model servers keyed by the layer (e.g. "L2")
+    /// they serve in place of Claude. Ignored in mock mode.
+    #[serde(default)]
+    pub local_models: HashMap<String, LocalModelConfig>,
+    
+    /// Worker processes of neurons with `settings.isolation: "process"`
+    #[serde(default)]
+    pub isolation: IsolationConfig,
+    
+    /// Refusing writes during database maintenance
+    #[serde(default)]
+    pub read_only: ReadOnlyConfig,
+    
+    /// How long signal payloads, chain summaries, cost records and dead
+    /// letters are kept
+    #[serde(default)]
+    pub retention: RetentionConfig,
+    
+    /// Timeouts of the provider calls neurons make, per layer or learned
+    /// from each neuron's latency
+    #[serde(default)]
+    pub timeouts: TimeoutConfig,
+    
+    /// Lifecycle of provider models: deprecations, retirements and the
+    /// models retired ones are remapped to
+    #[serde(default)]
+    pub models: ModelsConfig,
+    
+    /// The server database, migrated to this release's schema at startup
+    #[serde(default)]
+    pub database: DatabaseConfig,
+    
+    /// Branches a response may forward to and nodes a chain may spawn
+    #[serde(default)]
+    pub fan_out: FanOutConfig,
+    
+    /// Consciousness measurements pushed to a Gentle Singularity tracker
+    #[serde(default)]
+    pub singularity: SingularityConfig,
+    
+    /// Per-organization encryption of signal payloads and memory at rest
+    #[serde(default)]
+    pub encryption: EncryptionConfig,
+    
+    /// Children started from a parent's streamed output before it finishes
+    #[serde(default)]
+    pub speculation: SpeculationConfig,
+    
+    /// Kafka topics and inbound webhooks signals are taken in from
+    #[serde(default)]
+    pub ingestion: IngestionConfig,
+    
+    /// Benchmark signals scored after changes, and the gate on prompt changes
+    #[serde(default)]
+    pub quality: QualityConfig,
+    
+    /// Periodic state snapshots that debug views of past instants are
+    /// reconstructed from
+    #[serde(default)]
+    pub time_travel: TimeTravelConfig,
+    
+    /// Local heuristics and parked signals while every provider is down
+    #[serde(default)]
+    pub degraded: DegradedConfig,
+    
+    /// Differential privacy noise on usage reports requested with
+    /// `?dp_epsilon=`
+    #[serde(default)]
+    pub report_privacy: ReportPrivacyConfig,
+    
+    /// Copies of signal submissions sent to a staging server
+    #[serde(default)]
+    pub shadow: ShadowConfig,
+    
+    /// Tokens of each section of the prompts neurons send
+    #[serde(default)]
+    pub prompt_composition: PromptCompositionConfig,
+    
+    /// Neuron outputs held for a human to approve before they continue
+    #[serde(default)]
+    pub approvals: ApprovalConfig,
+    
+    /// Named chain templates submitted with just their variables
+    #[serde(default)]
+    pub templates: TemplatesConfig,
+    
+    /// CPU profiles and detection of async tasks stalling the executor
+    #[serde(default)]
+    pub profiling: ProfilingConfig,
+    
+    /// Multi-turn conversations whose chains see the turns before them
+    #[serde(default)]
+    pub sessions: SessionsConfig,
+    
+    /// Nightly per-organization usage files for the billing pipeline
+    #[serde(default)]
+    pub billing_export: BillingExportConfig,
+    
+    /// Limits on the tool calls of each chain
+    #[serde(default)]
+    pub tool_sandbox: ToolSandboxConfig,
 }
 
-/// Backward propagation configuration
+impl ServerConfig {
+    /// Every secret field, paired with its path in the config file
+    pub fn secrets_mut(&mut self) -> Vec<(&'static str, &mut SecretString)> {
+        let mut secrets = vec![("auth.jwt_secret", &mut self.auth.jwt_secret)];
+        if let Some(api_key) = self.claude.api_key.as_mut() {
+            secrets.push(("claude.api_key", api_key));
+        }
+        if let Some(signing_key) = self.receipts.signing_key.as_mut() {
+            secrets.push(("receipts.signing_key", signing_key));
+        }
+        if let AnchorConfig::Ethereum { private_key, .. } = &mut self.receipts.anchor {
+            secrets.push(("receipts.anchor.private_key", private_key));
+        }
+        if let ColdTierBackend::S3 { secret_access_key, .. } = &mut self.memory.tiering.backend {
+            secrets.push(("memory.tiering.backend.secret_access_key", secret_access_key));
+        }
+        if let Some(api_key) = self.shadow.api_key.as_mut() {
+            secrets.push(("shadow.api_key", api_key));
+        }
+        match &mut self.billing_export.destination {
+            BillingDestination::S3 { secret_access_key, .. } => {
+                secrets.push(("billing_export.destination.secret_access_key", secret_access_key));
+            }
+            BillingDestination::Webhook { secret, .. } => {
+                secrets.push(("billing_export.destination.secret", secret));
+            }
+            BillingDestination::Filesystem { .. } => {}
+        }
+        secrets
+    }
+    
+    /// Decrypt `enc:` secret values in place. The master key is only
+    /// requested when at least one secret is encrypted.
+    pub fn decrypt_secrets(&mut self, provider: &dyn MasterKeyProvider) -> crate::Result<()> {
+        let mut encrypted: Vec<_> = self.secrets_mut()
+            .into_iter()
+            .filter(|(_, secret)| secret.is_encrypted())
+            .collect();
+        if encrypted.is_empty() {
+            return Ok(());
+        }
+        
+        let cipher = SecretsCipher::from_provider(provider)?;
+        for (path, secret) in encrypted.iter_mut() {
+            let plaintext = cipher.decrypt(secret.expose())
+                .map_err(|e| crate::Error::Config(format!("{}: {}", path, e)))?;
+            **secret = SecretString::new(plaintext);
+        }
+        Ok(())
+    }
+}
+
+/// Per-user concurrent chain limits and fair scheduling configuration
 #[derive(Debug, Clone, Deserialize, Serialize)]
-pub struct BackwardPropagationConfig {
-    #[serde(default = "default_bp_enabled")]
+pub struct ChainLimitsConfig {
+    /// Enforce concurrent chain limits at submission
+    #[serde(default = "default_true")]
     pub enabled: bool,
     
-    #[serde(default = "default_learning_rate")]
-    pub learning_rate: f32,
+    /// Limit for users whose role has no explicit limit
+    #[serde(default = "default_max_concurrent_chains")]
+    pub default_max_concurrent_chains: u32,
     
-    #[serde(default = "default_pattern_threshold")]
-    pub pattern_threshold: usize,
+    /// Limits by role (admin, user, guest, api_key)
+    #[serde(default = "default_role_chain_limits")]
+    pub role_limits: HashMap<String, u32>,
     
-    #[serde(default = "default_adjustment_decay")]
-    pub adjustment_decay: f32,
+    /// Per-organization overrides (organization ID -> limit)
+    #[serde(default)]
+    pub org_limits: HashMap<String, u32>,
     
-    #[serde(default = "default_max_gradient_depth")]
-    pub max_gradient_depth: usize,
+    /// Signals processed concurrently across all users; slots are granted
+    /// round-robin between users when contended
+    #[serde(default = "default_max_concurrent_signals")]
+    pub max_concurrent_signals: usize,
 }
 
-impl Default for BackwardPropagationConfig {
+impl Default for ChainLimitsConfig {
     fn default() -> Self {
         Self {
-            enabled: default_bp_enabled(),
-            learning_rate: default_learning_rate(),
-            pattern_threshold: default_pattern_threshold(),
-            adjustment_decay: default_adjustment_decay(),
-            max_gradient_depth: default_max_gradient_depth(),
+            enabled: true,
+            default_max_concurrent_chains: default_max_concurrent_chains(),
+            role_limits: default_role_chain_limits(),
+            org_limits: HashMap::new(),
+            max_concurrent_signals: default_max_concurrent_signals(),
         }
     }
 }
 
-/// Memory configuration
+/// Outbound webhook configuration
 #[derive(Debug, Clone, Deserialize, Serialize)]
-pub struct MemoryConfig {
-    /// Enable memory system
-    #[serde(default = "default_false")]
+pub struct WebhookConfig {
+    /// Deliver webhook events
+    #[serde(default = "default_true")]
     pub enabled: bool,
     
-    /// Database path
-    #[serde(default = "default_memory_database_path")]
-    pub database_path: String,
+    /// Delivery attempts before a delivery is dead-lettered
+    #[serde(default = "default_webhook_max_attempts")]
+    pub max_attempts: u32,
     
-    /// Cleanup configuration
+    /// Delay before the first retry; doubles on each subsequent retry
+    #[serde(default = "default_webhook_initial_backoff_ms")]
+    pub initial_backoff_ms: u64,
+    
+    /// Upper bound on the retry delay
+    #[serde(default = "default_webhook_max_backoff_ms")]
+    pub max_backoff_ms: u64,
+    
+    /// Per-request timeout
+    #[serde(default = "default_webhook_timeout_secs")]
+    pub timeout_secs: u64,
+    
+    /// Maximum serialized payload size; larger payloads are truncated and
+    /// link to the full result instead
+    #[serde(default = "default_webhook_max_payload_bytes")]
+    pub max_payload_bytes: usize,
+    
+    /// Maximum webhooks registered per organization
+    #[serde(default = "default_webhook_max_per_org")]
+    pub max_webhooks_per_org: usize,
+    
+    /// Externally reachable base URL used for links in payloads
     #[serde(default)]
-    pub cleanup: MemoryCleanupConfig,
+    pub public_base_url: Option<String>,
 }
 
-impl Default for MemoryConfig {
+impl Default for WebhookConfig {
     fn default() -> Self {
         Self {
-            enabled: false,
-            database_path: default_memory_database_path(),
-            cleanup: MemoryCleanupConfig::default(),
+            enabled: true,
+            max_attempts: default_webhook_max_attempts(),
+            initial_backoff_ms: default_webhook_initial_backoff_ms(),
+            max_backoff_ms: default_webhook_max_backoff_ms(),
+            timeout_secs: default_webhook_timeout_secs(),
+            max_payload_bytes: default_webhook_max_payload_bytes(),
+            max_webhooks_per_org: default_webhook_max_per_org(),
+            public_base_url: None,
         }
     }
 }
 
-/// Memory cleanup configuration
+/// Load-based self-organization: clone neurons of overloaded layers and
+/// remove the clones once load subsides
 #[derive(Debug, Clone, Deserialize, Serialize)]
-pub struct MemoryCleanupConfig {
-    /// Retention days
-    #[serde(default = "default_retention_days")]
-    pub retention_days: u32,
+pub struct ScalingConfig {
+    /// Evaluate layer load and scale automatically
+    #[serde(default = "default_false")]
+    pub enabled: bool,
     
-    /// Minimum importance to keep
-    #[serde(default = "default_min_importance")]
-    pub min_importance: f32,
+    /// Signals a single neuron processes at once; further signals queue
+    #[serde(default = "default_scaling_neuron_concurrency")]
+    pub neuron_concurrency: usize,
+    
+    /// How often layer load is evaluated
+    #[serde(default = "default_scaling_evaluation_interval_ms")]
+    pub evaluation_interval_ms: u64,
+    
+    /// How long a layer must stay over (or under) thresholds before scaling
+    #[serde(default = "default_scaling_sustained_ms")]
+    pub sustained_ms: u64,
+    
+    /// Minimum time between two scaling actions on the same layer
+    #[serde(default = "default_scaling_cooldown_ms")]
+    pub cooldown_ms: u64,
+    
+    /// Scale up when signals queued or in flight across a layer exceed this
+    #[serde(default = "default_scaling_scale_up_queue_depth")]
+    pub scale_up_queue_depth: usize,
+    
+    /// Scale up when the layer's p95 latency (queueing included) exceeds this
+    #[serde(default = "default_scaling_scale_up_p95_ms")]
+    pub scale_up_p95_ms: u64,
+    
+    /// Scale down when layer queue depth is at most this...
+    #[serde(default = "default_scaling_scale_down_queue_depth")]
+    pub scale_down_queue_depth: usize,
+    
+    /// ...and p95 latency is at most this
+    #[serde(default = "default_scaling_scale_down_p95_ms")]
+    pub scale_down_p95_ms: u64,
+    
+    /// Window latency percentiles are computed over
+    #[serde(default = "default_scaling_latency_window_ms")]
+    pub latency_window_ms: u64,
+    
+    /// Maximum clones alive in one layer
+    #[serde(default = "default_scaling_max_clones_per_layer")]
+    pub max_clones_per_layer: usize,
+    
+    /// Scaling down never leaves a layer with fewer neurons than this
+    #[serde(default = "default_scaling_min_neurons_per_layer")]
+    pub min_neurons_per_layer: usize,
+    
+    /// Do not scale up once this fraction of the hourly or daily cost budget is spent
+    #[serde(default = "default_scaling_max_budget_utilization")]
+    pub max_budget_utilization: f64,
+    
+    /// How long a removed clone may take to finish in-flight signals
+    #[serde(default = "default_scaling_drain_timeout_ms")]
+    pub drain_timeout_ms: u64,
 }
 
-impl Default for MemoryCleanupConfig {
+impl Default for ScalingConfig {
     fn default() -> Self {
         Self {
-            retention_days: default_retention_days(),
-            min_importance: default_min_importance(),
+            enabled: false,
+            neuron_concurrency: default_scaling_neuron_concurrency(),
+            evaluation_interval_ms: default_scaling_evaluation_interval_ms(),
+            sustained_ms: default_scaling_sustained_ms(),
+            cooldown_ms: default_scaling_cooldown_ms(),
+            scale_up_queue_depth: default_scaling_scale_up_queue_depth(),
+            scale_up_p95_ms: default_scaling_scale_up_p95_ms(),
+            scale_down_queue_depth: default_scaling_scale_down_queue_depth(),
+            scale_down_p95_ms: default_scaling_scale_down_p95_ms(),
+            latency_window_ms: default_scaling_latency_window_ms(),
+            max_clones_per_layer: default_scaling_max_clones_per_layer(),
+            min_neurons_per_layer: default_scaling_min_neurons_per_layer(),
+            max_budget_utilization: default_scaling_max_budget_utilization(),
+            drain_timeout_ms: default_scaling_drain_timeout_ms(),
         }
     }
 }
 
-/// Individual neuron configuration
+/// Checks a neuron's response must pass before its signals are forwarded
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct ValidationConfig {
+    /// Validators run against every response
+    #[serde(default)]
+    pub validators: Vec<ValidatorConfig>,
+    
+    /// Times the neuron is re-prompted with the failure reason before the
+    /// signal fails
+    #[serde(default)]
+    pub max_retries: u32,
+}
+
+/// A built-in response validator
 #[derive(Debug, Clone, Deserialize, Serialize)]
-pub struct NeuronConfig {
-    pub id: String,
-    pub layer: String,
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ValidatorConfig {
+    /// Parse fenced code blocks in their declared language
+    CodeSyntax {
+        /// Languages to check; empty checks every supported language
+        #[serde(default)]
+        languages: Vec<String>,
+    },
     
-    /// Command to spawn neuron process (for CLI mode)
-    #[serde(default = "default_claude_command")]
-    pub claude_command: String,
+    /// Validate JSON output against a JSON Schema
+    JsonSchema {
+        schema: serde_json::Value,
+    },
     
-    /// System prompt for the neuron
-    pub system_prompt: Option<String>,
+    /// Reject responses longer than `max_chars` characters
+    MaxLength {
+        max_chars: usize,
+    },
+}
+
+/// Ordered recovery steps, tried until one succeeds
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct RecoveryPlaybookConfig {
+    #[serde(default)]
+    pub steps: Vec<RecoveryStepConfig>,
+}
+
+/// A built-in recovery step
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RecoveryStepConfig {
+    /// Process the signal again on the same neuron, with exponential backoff
+    Retry {
+        #[serde(default = "default_recovery_retry_attempts")]
+        attempts: u32,
+        #[serde(default = "default_recovery_initial_backoff_ms")]
+        initial_backoff_ms: u64,
+        #[serde(default = "default_recovery_max_backoff_ms")]
+        max_backoff_ms: u64,
+    },
     
-    /// Forward connections (neurons this one sends to)
-    pub forward_connections: Vec<String>,
+    /// Send the neuron's prompt to another model
+    FallbackModel {
+        model: String,
+    },
     
-    /// Backward connections (neurons this one receives errors from)
-    pub backward_connections: Vec<String>,
+    /// Process the signal on the neuron's clones, or the neuron it was
+    /// cloned from
+    RerouteSibling,
     
-    /// Optional neuron-specific configuration
+    /// Ask a model for a short best-effort answer and forward it marked as
+    /// degraded
+    SummarizeAndDegrade {
+        #[serde(default = "default_output_reformat_model")]
+        model: String,
+        /// Longest answer asked for, in characters
+        #[serde(default = "default_recovery_summary_chars")]
+        max_chars: usize,
+    },
+    
+    /// Move the signal to the dead-letter queue; ends the playbook
+    DeadLetter,
+}
+
+/// A local model behind an OpenAI-compatible completions endpoint. Layers
+/// naming the same server and model share one batch queue.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct LocalModelConfig {
+    /// Server root, e.g. "http://gpu-box:8000"
+    pub base_url: String,
+    pub model: String,
+    /// Most requests coalesced into one batch
+    #[serde(default = "default_local_max_batch_size")]
+    pub max_batch_size: usize,
+    /// Longest a request waits for its batch to fill
+    #[serde(default = "default_local_max_wait_ms")]
+    pub max_wait_ms: u64,
+    /// Requests with less than `max_wait_ms` plus this margin left before
+    /// their signal's deadline skip the batch wait
+    #[serde(default = "default_local_deadline_margin_ms")]
+    pub deadline_margin_ms: u64,
+    /// Send a batch as one request with a list of prompts instead of one
+    /// request per prompt
     #[serde(default)]
-    pub settings: HashMap<String, serde_json::Value>,
+    pub batch_endpoint: bool,
+    #[serde(default = "default_max_tokens")]
+    pub max_tokens: u32,
+    #[serde(default = "default_local_timeout_secs")]
+    pub timeout_secs: u64,
+}
+
+/// Where a neuron's processing loop runs
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IsolationMode {
+    /// On the server's own runtime
+    #[default]
+    InProcess,
+    /// In a supervised child process
+    Process,
+}
+
+impl IsolationMode {
+    /// The neuron's `settings.isolation`, in-process when unset
+    pub fn for_neuron(neuron: &NeuronConfig) -> crate::Result<Self> {
+        match neuron.settings.get("isolation") {
+            Some(mode) => serde_json::from_value(mode.clone()).map_err(|e| {
+                crate::Error::Config(format!("Invalid isolation mode for neuron {}: {}", neuron.id, e))
+            }),
+            None => Ok(Self::InProcess),
+        }
+    }
 }
 
-/// Monitoring configuration
-#[derive(Debug, Clone, Deserialize, Serialize, Default)]
-pub struct MonitoringConfig {
-    /// Enable metrics collection
-    #[serde(default = "default_true")]
-    pub enabled: bool,
+/// Limits and restart policy of neuron worker processes
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct IsolationConfig {
+    /// Worker executable; defaults to `hal9-neuron-worker` next to the
+    /// server binary
+    #[serde(default)]
+    pub worker_command: Option<String>,
+    /// Data segment limit of each worker. A worker exceeding it fails its
+    /// allocations and is restarted.
+    #[serde(default = "default_worker_max_memory_mb")]
+    pub max_memory_mb: u64,
+    /// CPU time limit of each worker, unlimited when unset
+    #[serde(default)]
+    pub max_cpu_secs: Option<u64>,
+    /// Delay before the first restart of a crashed worker, doubled on each
+    /// further crash until a signal is processed again
+    #[serde(default = "default_worker_restart_initial_backoff_ms")]
+    pub restart_initial_backoff_ms: u64,
+    #[serde(default = "default_worker_restart_max_backoff_ms")]
+    pub restart_max_backoff_ms: u64,
+}
+
+impl Default for IsolationConfig {
+    fn default() -> Self {
+        Self {
+            worker_command: None,
+            max_memory_mb: default_worker_max_memory_mb(),
+            max_cpu_secs: None,
+            restart_initial_backoff_ms: default_worker_restart_initial_backoff_ms(),
+            restart_max_backoff_ms: default_worker_restart_max_backoff_ms(),
+        }
+    }
+}
+
+/// How a chain's final output is brought into the format its submitter
+/// asked for
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct OutputFormatConfig {
+    /// Model asked to reformat an output that failed its format check
+    #[serde(default = "default_output_reformat_model")]
+    pub reformat_model: String,
     
-    /// Metrics export interval in seconds
-    #[serde(default = "default_metrics_interval")]
-    pub metrics_interval: u64,
+    /// Reformat attempts before the output is returned as degraded
+    #[serde(default = "default_output_max_reformat_retries")]
+    pub max_reformat_retries: u32,
+}
+
+impl Default for OutputFormatConfig {
+    fn default() -> Self {
+        Self {
+            reformat_model: default_output_reformat_model(),
+            max_reformat_retries: default_output_max_reformat_retries(),
+        }
+    }
+}
+
+/// Warm pools of neuron instances that are slow to start, such as WASM
+/// plugins (instantiation) or process-backed neurons (spawn)
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct WarmPoolConfig {
+    /// Idle instances kept ready by neuron type; types not listed are
+    /// instantiated on demand
+    #[serde(default)]
+    pub sizes: HashMap<String, usize>,
     
-    /// Log level (trace, debug, info, warn, error)
-    #[serde(default = "default_log_level")]
-    pub log_level: String,
+    /// Seconds an instance may sit idle before it is shut down and replaced,
+    /// bounding the memory long-lived instances accumulate
+    #[serde(default = "default_warm_pool_idle_ttl_secs")]
+    pub idle_ttl_secs: u64,
+    
+    /// Milliseconds between background refills and recycling passes
+    #[serde(default = "default_warm_pool_refresh_interval_ms")]
+    pub refresh_interval_ms: u64,
+}
+
+impl Default for WarmPoolConfig {
+    fn default() -> Self {
+        Self {
+            sizes: HashMap::new(),
+            idle_ttl_secs: default_warm_pool_idle_ttl_secs(),
+            refresh_interval_ms: default_warm_pool_refresh_interval_ms(),
+        }
+    }
+}
+
+/// Dependency health probes
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct HealthConfig {
+    /// How long a probe result is reused before the dependency is checked again
+    #[serde(default = "default_health_cache_ttl_ms")]
+    pub cache_ttl_ms: u64,
+    
+    /// Time a probe may take before its dependency counts as unhealthy
+    #[serde(default = "default_health_probe_timeout_ms")]
+    pub probe_timeout_ms: u64,
+    
+    /// Per-probe timeouts keyed by probe name (e.g. "claude"), overriding
+    /// `probe_timeout_ms`
+    #[serde(default)]
+    pub timeouts_ms: HashMap<String, u64>,
+    
+    /// Database round trips slower than this are reported as degraded
+    #[serde(default = "default_health_database_slow_ms")]
+    pub database_slow_ms: u64,
+    
+    /// URL probed with a HEAD request when Claude has not answered recently
+    #[serde(default = "default_health_claude_endpoint")]
+    pub claude_endpoint: String,
+    
+    /// A Claude response this recent proves reachability without a request
+    #[serde(default = "default_health_claude_success_max_age_secs")]
+    pub claude_success_max_age_secs: u64,
+    
+    /// Disk usage above this percentage is reported as degraded...
+    #[serde(default = "default_health_disk_degraded_percent")]
+    pub disk_degraded_percent: f64,
+    
+    /// ...and above this as unhealthy
+    #[serde(default = "default_health_disk_unhealthy_percent")]
+    pub disk_unhealthy_percent: f64,
+}
+
+impl Default for HealthConfig {
+    fn default() -> Self {
+        Self {
+            cache_ttl_ms: default_health_cache_ttl_ms(),
+            probe_timeout_ms: default_health_probe_timeout_ms(),
+            timeouts_ms: HashMap::new(),
+            database_slow_ms: default_health_database_slow_ms(),
+            claude_endpoint: default_health_claude_endpoint(),
+            claude_success_max_age_secs: default_health_claude_success_max_age_secs(),
+            disk_degraded_percent: default_health_disk_degraded_percent(),
+            disk_unhealthy_percent: default_health_disk_unhealthy_percent(),
+        }
+    }
+}
+
+impl HealthConfig {
+    /// Timeout of the probe called `name`
+    pub fn timeout_ms(&self, name: &str) -> u64 {
+        self.timeouts_ms.get(name).copied().unwrap_or(self.probe_timeout_ms)
+    }
+}
+
+/// Goal tracking
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct GoalsConfig {
+    /// Neuron that scores goal results against their success criteria.
+    /// Without one, criteria are never evaluated.
+    #[serde(default)]
+    pub evaluator: Option<String>,
+    
+    /// Attempts an adaptive goal makes before giving up on unmet criteria
+    #[serde(default = "default_goals_max_rounds")]
+    pub max_rounds: u32,
+}
+
+impl Default for GoalsConfig {
+    fn default() -> Self {
+        Self {
+            evaluator: None,
+            max_rounds: default_goals_max_rounds(),
+        }
+    }
+}
+
+/// Layer pause configuration
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct LayerPauseConfig {
+    /// File paused layers are saved to, so a restart keeps them paused
+    #[serde(default = "default_layer_pause_state_path")]
+    pub state_path: String,
+    
+    /// Signals held per paused layer; further signals go to the dead-letter queue
+    #[serde(default = "default_layer_pause_max_held")]
+    pub max_held: usize,
+}
+
+impl Default for LayerPauseConfig {
+    fn default() -> Self {
+        Self {
+            state_path: default_layer_pause_state_path(),
+            max_held: default_layer_pause_max_held(),
+        }
+    }
+}
+
+/// Read-only mode configuration
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ReadOnlyConfig {
+    /// Start in read-only mode
+    #[serde(default)]
+    pub enabled: bool,
+    
+    /// Reason reported while read-only from boot
+    #[serde(default)]
+    pub reason: Option<String>,
+    
+    /// Seconds clients are told to wait before retrying a refused write
+    #[serde(default = "default_read_only_retry_after_secs")]
+    pub retry_after_secs: u64,
+    
+    /// What happens to memory writes of chains still running
+    #[serde(default)]
+    pub memory_writes: MemoryWritePolicy,
+    
+    /// Memory writes held at most; further writes are skipped
+    #[serde(default = "default_read_only_max_held_writes")]
+    pub max_held_writes: usize,
+    
+    /// Read-only copies of databases, by database name ("auth" or
+    /// "memory"), that serve reads while the mode is on
+    #[serde(default)]
+    pub replicas: HashMap<String, String>,
+}
+
+impl Default for ReadOnlyConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            reason: None,
+            retry_after_secs: default_read_only_retry_after_secs(),
+            memory_writes: MemoryWritePolicy::default(),
+            max_held_writes: default_read_only_max_held_writes(),
+            replicas: HashMap::new(),
+        }
+    }
+}
+
+/// Memory writes made while the server is read-only
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MemoryWritePolicy {
+    /// Dropped; the neuron sees the write fail
+    #[default]
+    Skip,
+    /// Kept in memory and written when the mode is turned off
+    Hold,
+}
+
+/// Retention configuration
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RetentionConfig {
+    /// Run the retention janitor
+    #[serde(default)]
+    pub enabled: bool,
+    
+    /// Seconds between janitor runs
+    #[serde(default = "default_retention_interval_secs")]
+    pub interval_secs: u64,
+    
+    /// Days finished chains and signal log rows keep their full payloads
+    /// before they are archived and deleted
+    #[serde(default = "default_retention_payload_days")]
+    pub payload_days: u32,
+    
+    /// Days the summary of an archived chain stays in memory; older chains
+    /// are looked up in the archive files
+    #[serde(default = "default_retention_chain_summary_days")]
+    pub chain_summary_days: u32,
+    
+    /// Days cost records stay in the signal log
+    #[serde(default = "default_retention_cost_record_days")]
+    pub cost_record_days: u32,
+    
+    /// Days dead letters are kept
+    #[serde(default = "default_retention_dead_letter_days")]
+    pub dead_letter_days: u32,
+    
+    /// Directory archived payloads are written to, as zstd-compressed JSON
+    /// lines. A mounted object-store bucket works as well as a local disk.
+    #[serde(default = "default_retention_archive_dir")]
+    pub archive_dir: String,
+    
+    /// SQLite signal log whose `signals` and cost rows the janitor prunes
+    #[serde(default)]
+    pub signal_log_path: Option<String>,
+}
+
+impl Default for RetentionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval_secs: default_retention_interval_secs(),
+            payload_days: default_retention_payload_days(),
+            chain_summary_days: default_retention_chain_summary_days(),
+            cost_record_days: default_retention_cost_record_days(),
+            dead_letter_days: default_retention_dead_letter_days(),
+            archive_dir: default_retention_archive_dir(),
+            signal_log_path: None,
+        }
+    }
+}
+
+/// Provider model lifecycle
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ModelsConfig {
+    /// Model called instead of each retired one, e.g.
+    /// `claude-3-opus-20240229: claude-3-5-sonnet-latest`
+    #[serde(default)]
+    pub remap: HashMap<String, String>,
+    
+    /// Models missing from the built-in registry, or overriding its entry
+    #[serde(default)]
+    pub registry: Vec<ModelEntryConfig>,
+    
+    /// Interval between checks of the configured models against the
+    /// registry, in seconds
+    #[serde(default = "default_model_check_interval_secs")]
+    pub check_interval_secs: u64,
+}
+
+impl Default for ModelsConfig {
+    fn default() -> Self {
+        Self {
+            remap: HashMap::new(),
+            registry: Vec::new(),
+            check_interval_secs: default_model_check_interval_secs(),
+        }
+    }
+}
+
+/// A model's lifecycle status
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ModelLifecycle {
+    Active,
+    /// Still served until its sunset date
+    Deprecated,
+    Retired,
+}
+
+/// A registry entry
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ModelEntryConfig {
+    pub id: String,
+    pub status: ModelLifecycle,
+    /// Day a deprecated model is retired
+    #[serde(default)]
+    pub sunset: Option<chrono::NaiveDate>,
+}
+
+/// Limits on the branches a chain spawns. Unset limits don't apply.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct FanOutConfig {
+    /// Forward signals one response may spawn, keyed by the layer of the
+    /// responding neuron (e.g. "L4")
+    #[serde(default)]
+    pub max_children: HashMap<String, u32>,
+    
+    /// Forward signals one response may spawn on layers without an entry in
+    /// `max_children`
+    #[serde(default)]
+    pub default_max_children: Option<u32>,
+    
+    /// Signals a chain may process in total, its root included
+    #[serde(default)]
+    pub node_budget: Option<u64>,
+    
+    /// Fail the chain instead of dropping the branches over a limit
+    #[serde(default)]
+    pub strict: bool,
+}
+
+/// Speculative execution of child signals. A neuron on one of `layers`
+/// streams its response, and children of a forward instruction complete in
+/// the partial output start before the response is finished.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SpeculationConfig {
+    /// Layers of the neurons whose children may start early (e.g. "L4")
+    #[serde(default)]
+    pub layers: Vec<String>,
+    
+    /// Dollars cancelled speculative children may cost in an hour before
+    /// speculation stops until the hour's waste falls below it
+    #[serde(default = "default_speculation_max_wasted_per_hour")]
+    pub max_wasted_per_hour: f64,
+}
+
+impl Default for SpeculationConfig {
+    fn default() -> Self {
+        Self {
+            layers: Vec::new(),
+            max_wasted_per_hour: default_speculation_max_wasted_per_hour(),
+        }
+    }
+}
+
+/// Sources chains are started from besides the HTTP API
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct IngestionConfig {
+    /// Kafka topics and webhook receivers, each under a unique name
+    #[serde(default)]
+    pub sources: Vec<IngestSourceConfig>,
+    
+    /// Seconds a message's dedup key is remembered; the same key arriving
+    /// again within them starts no second chain
+    #[serde(default = "default_ingestion_dedup_window_secs")]
+    pub dedup_window_secs: u64,
+    
+    /// SQLite database messages that could not be mapped are kept in
+    #[serde(default = "default_ingestion_quarantine_path")]
+    pub quarantine_path: String,
+}
+
+impl Default for IngestionConfig {
+    fn default() -> Self {
+        Self {
+            sources: Vec::new(),
+            dedup_window_secs: default_ingestion_dedup_window_secs(),
+            quarantine_path: default_ingestion_quarantine_path(),
+        }
+    }
+}
+
+/// One source of inbound messages and how they become signals
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct IngestSourceConfig {
+    /// Name the source is paused, resumed and reported by
+    pub name: String,
+    
+    /// Where messages come from
+    pub transport: IngestTransport,
+    
+    /// How a message becomes the root signal of a chain
+    pub mapping: IngestMapping,
+    
+    /// Cost tags every chain of the source carries, e.g. `source: orders`
+    #[serde(default)]
+    pub tags: HashMap<String, String>,
+    
+    /// Messages accepted per minute; unlimited if unset
+    #[serde(default)]
+    pub max_per_minute: Option<u32>,
+    
+    /// Spend of chains carrying `tags` above which no messages are taken
+    /// in until the period resets
+    #[serde(default)]
+    pub budget: Option<IngestBudgetConfig>,
+}
+
+/// Transport of an ingestion source
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum IngestTransport {
+    /// A topic consumed by a consumer group; needs the `kafka` feature
+    Kafka {
+        /// Bootstrap servers, e.g. "localhost:9092"
+        brokers: String,
+        topic: String,
+        /// Consumer group; a message's offset is committed once it is handled
+        group: String,
+        /// Further librdkafka settings, e.g. "security.protocol"
+        #[serde(default)]
+        settings: HashMap<String, String>,
+    },
+    /// Signed POSTs to `/api/v1/ingest/webhooks/<name>`
+    Webhook {
+        /// Environment variable holding the HMAC-SHA256 secret
+        secret_env: String,
+        /// Header carrying the hex signature, with or without `sha256=`
+        #[serde(default = "default_ingest_signature_header")]
+        signature_header: String,
+        /// Header carrying the unix time the sender signed as
+        /// "{timestamp}.{body}"; without one the body alone is signed
+        #[serde(default)]
+        timestamp_header: Option<String>,
+    },
+}
+
+/// Templates mapping a JSON message to a signal. `{{/path}}` stands for the
+/// value at that JSON pointer in the message.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct IngestMapping {
+    /// Neuron the signal is sent to
+    pub to: String,
+    
+    /// Signal content
+    pub content: String,
+    
+    /// Sender recorded on the signal; the source's name if unset
+    #[serde(default)]
+    pub from: Option<String>,
+    
+    /// Cost tags taken from the message, added to the source's own
+    #[serde(default)]
+    pub tags: HashMap<String, String>,
+    
+    /// Key duplicates are recognized by; the SHA-256 of the raw message if
+    /// unset
+    #[serde(default)]
+    pub dedup_key: Option<String>,
+}
+
+/// Budget of an ingestion source
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct IngestBudgetConfig {
+    /// Budget per period in USD
+    pub usd: f64,
+    
+    /// Reset cadence, in UTC
+    #[serde(default = "default_ingest_budget_period")]
+    pub period: BudgetPeriod,
+}
+
+/// Quality benchmarks: curated signals run against the neurons and scored,
+/// kept in the server database (`database.url`)
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct QualityConfig {
+    /// Keep benchmarks and run them; needs a database
+    #[serde(default)]
+    pub enabled: bool,
+    
+    /// Run the benchmarks when the server starts with a topology or model
+    /// mapping they have not been run against, and after topology patches
+    #[serde(default = "default_true")]
+    pub run_on_change: bool,
+    
+    /// Refuse topology patches whose changed neurons score significantly
+    /// worse on their benchmarks than the running ones
+    #[serde(default)]
+    pub gate: bool,
+    
+    /// p-value below which a layer's drop in score counts as significant
+    #[serde(default = "default_quality_significance")]
+    pub significance: f64,
+    
+    /// Drop in a layer's mean score, from 0.0 to 1.0, below which it is
+    /// not flagged however significant
+    #[serde(default = "default_quality_min_drop")]
+    pub min_drop: f64,
+    
+    /// Model judging rubric benchmarks; `claude.model` if unset
+    #[serde(default)]
+    pub judge_model: Option<String>,
+}
+
+impl Default for QualityConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            run_on_change: true,
+            gate: false,
+            significance: default_quality_significance(),
+            min_drop: default_quality_min_drop(),
+            judge_model: None,
+        }
+    }
+}
+
+/// State snapshots for reconstructing the server at a past instant
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TimeTravelConfig {
+    /// Take snapshots and answer `/api/v1/debug/at`
+    #[serde(default)]
+    pub enabled: bool,
+    
+    /// Seconds between snapshots of queue depths, circuit breakers and
+    /// budgets. Queue depths in between are interpolated from chain steps;
+    /// breakers and budgets are as of the snapshot before.
+    #[serde(default = "default_snapshot_interval_secs")]
+    pub snapshot_interval_secs: u64,
+}
+
+impl Default for TimeTravelConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            snapshot_interval_secs: default_snapshot_interval_secs(),
+        }
+    }
+}
+
+/// Degraded mode, entered while the circuit breaker of every neuron is
+/// open or when forced by an operator
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct DegradedConfig {
+    /// Enter degraded mode on its own once every breaker is open
+    #[serde(default = "default_true")]
+    pub auto: bool,
+    
+    /// Layers whose neurons split their task with `templates` while
+    /// degraded; signals for other layers are parked until providers recover
+    #[serde(default = "default_degraded_decompose_layers")]
+    pub decompose_layers: Vec<String>,
+    
+    /// Tasks a decomposing neuron forwards, `{task}` replaced by its input.
+    /// They are assigned to its forward connections in turn.
+    #[serde(default = "default_degraded_templates")]
+    pub templates: Vec<String>,
+    
+    /// Milliseconds between retries of the oldest parked signal, which end
+    /// degraded mode once one gets through
+    #[serde(default = "default_degraded_probe_interval_ms")]
+    pub probe_interval_ms: u64,
+    
+    /// Signals parked at most; further ones go to the dead-letter queue
+    #[serde(default = "default_degraded_max_parked")]
+    pub max_parked: usize,
+}
+
+impl Default for DegradedConfig {
+    fn default() -> Self {
+        Self {
+            auto: true,
+            decompose_layers: default_degraded_decompose_layers(),
+            templates: default_degraded_templates(),
+            probe_interval_ms: default_degraded_probe_interval_ms(),
+            max_parked: default_degraded_max_parked(),
+        }
+    }
+}
+
+/// Laplace noise added to the counts and sums of exported usage reports.
+/// Sensitivities bound what one chain is taken to contribute.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ReportPrivacyConfig {
+    /// Epsilon an API key may spend on noised reports in total; queries
+    /// past it are refused
+    #[serde(default = "default_privacy_max_epsilon")]
+    pub max_epsilon: f64,
+    
+    /// Epsilon charged for a report requested without `dp_epsilon`
+    #[serde(default = "default_privacy_default_epsilon")]
+    pub default_epsilon: f64,
+    
+    /// Noised counts below this are withheld, with the rows they count
+    #[serde(default = "default_privacy_suppression_threshold")]
+    pub suppression_threshold: u64,
+    
+    /// Tokens one chain is taken to add to a sum at most
+    #[serde(default = "default_privacy_max_tokens_per_chain")]
+    pub max_tokens_per_chain: u64,
+    
+    /// Cost in USD one chain is taken to add to a sum at most
+    #[serde(default = "default_privacy_max_cost_per_chain")]
+    pub max_cost_per_chain: f64,
+}
+
+impl Default for ReportPrivacyConfig {
+    fn default() -> Self {
+        Self {
+            max_epsilon: default_privacy_max_epsilon(),
+            default_epsilon: default_privacy_default_epsilon(),
+            suppression_threshold: default_privacy_suppression_threshold(),
+            max_tokens_per_chain: default_privacy_max_tokens_per_chain(),
+            max_cost_per_chain: default_privacy_max_cost_per_chain(),
+        }
+    }
+}
+
+/// Shadowing of production signal submissions to a staging server. A
+/// sampled share of submissions is sent again, redacted and tagged, once
+/// production has accepted it; nothing waits for the staging server.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ShadowConfig {
+    /// Send shadows. `POST /api/v1/admin/shadow` turns them off and on
+    /// without a restart.
+    #[serde(default)]
+    pub enabled: bool,
+    
+    /// Base URL of the staging server, e.g. "https://staging.hal9.example"
+    #[serde(default)]
+    pub target_url: Option<String>,
+    
+    /// API key the shadows are submitted to staging with
+    #[serde(default)]
+    pub api_key: Option<SecretString>,
+    
+    /// Share of submissions shadowed, 0.0 - 1.0
+    #[serde(default = "default_shadow_sample_rate")]
+    pub sample_rate: f64,
+    
+    /// Endpoints whose submissions are shadowed
+    #[serde(default)]
+    pub endpoints: ShadowEndpointsConfig,
+    
+    /// Shadows in flight at most; submissions past it are not shadowed
+    #[serde(default = "default_shadow_max_in_flight")]
+    pub max_in_flight: usize,
+    
+    /// Timeout of each request to staging in milliseconds
+    #[serde(default = "default_shadow_timeout_ms")]
+    pub timeout_ms: u64,
+    
+    /// Node budget of a shadowed chain at most, see `fan_out.node_budget`
+    #[serde(default = "default_shadow_node_budget")]
+    pub node_budget: u64,
+    
+    /// Cost tags added to every shadow, over the submission's own. Staging
+    /// must list their keys in `claude.cost_controls.tags.allowed_keys`.
+    #[serde(default = "default_shadow_tags")]
+    pub tags: HashMap<String, String>,
+}
+
+impl Default for ShadowConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            target_url: None,
+            api_key: None,
+            sample_rate: default_shadow_sample_rate(),
+            endpoints: ShadowEndpointsConfig::default(),
+            max_in_flight: default_shadow_max_in_flight(),
+            timeout_ms: default_shadow_timeout_ms(),
+            node_budget: default_shadow_node_budget(),
+            tags: default_shadow_tags(),
+        }
+    }
+}
+
+/// Whether each submission endpoint is shadowed
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ShadowEndpointsConfig {
+    /// `POST /api/v1/signal`
+    #[serde(default = "default_true")]
+    pub signal: bool,
+    
+    /// `POST /api/v1/signals/batch`
+    #[serde(default)]
+    pub signals_batch: bool,
+}
+
+impl Default for ShadowEndpointsConfig {
+    fn default() -> Self {
+        Self {
+            signal: true,
+            signals_batch: false,
+        }
+    }
+}
+
+/// Accounting of the tokens each section of a neuron's prompt takes, for
+/// `GET /api/v1/reports/prompt-composition`. It only measures; prompts are
+/// sent as they would be without it.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct PromptCompositionConfig {
+    /// Count the sections of every prompt sent to a provider
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    
+    /// Neurons whose static sections, the system prompt and layer
+    /// instructions, average more than this share of their prompt tokens
+    /// are flagged in the report
+    #[serde(default = "default_static_share_threshold")]
+    pub static_share_threshold: f64,
+    
+    /// Price of a prompt token read from the provider's prompt cache, as a
+    /// share of the price of an uncached one; caching savings are projected
+    /// with it
+    #[serde(default = "default_cache_read_ratio")]
+    pub cache_read_ratio: f64,
+}
+
+impl Default for PromptCompositionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            static_share_threshold: default_static_share_threshold(),
+            cache_read_ratio: default_cache_read_ratio(),
+        }
+    }
+}
+
+/// Approval gates, which hold a neuron's output and the signals it forwards
+/// until someone approves or rejects it through `/api/v1/approvals`
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ApprovalConfig {
+    /// Seconds an output waits for a decision before `timeout_action` is
+    /// taken on it
+    #[serde(default = "default_approval_timeout_secs")]
+    pub timeout_secs: u64,
+    
+    /// What happens to an output nobody decided on in time
+    #[serde(default)]
+    pub timeout_action: ApprovalTimeoutAction,
+    
+    /// Outputs waiting at most; further ones are rejected as they arrive
+    #[serde(default = "default_approval_max_pending")]
+    pub max_pending: usize,
+}
+
+impl Default for ApprovalConfig {
+    fn default() -> Self {
+        Self {
+            timeout_secs: default_approval_timeout_secs(),
+            timeout_action: ApprovalTimeoutAction::default(),
+            max_pending: default_approval_max_pending(),
+        }
+    }
+}
+
+/// Decision taken on an approval that timed out
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ApprovalTimeoutAction {
+    /// The branch fails
+    #[default]
+    Reject,
+    /// The output continues as the neuron produced it
+    Approve,
+}
+
+/// Chain templates: named, versioned request shapes with typed variables,
+/// kept in the server database (`database.url`)
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TemplatesConfig {
+    /// Keep templates and accept submissions of them; needs a database
+    #[serde(default)]
+    pub enabled: bool,
+    
+    /// Files given for a file variable of at most this many bytes are
+    /// inlined into the content; larger ones are stored as artifacts and
+    /// the content links to them
+    #[serde(default = "default_template_inline_file_bytes")]
+    pub inline_file_bytes: usize,
+    
+    /// Largest file a file variable takes, in bytes
+    #[serde(default = "default_template_max_file_bytes")]
+    pub max_file_bytes: usize,
+}
+
+impl Default for TemplatesConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            inline_file_bytes: default_template_inline_file_bytes(),
+            max_file_bytes: default_template_max_file_bytes(),
+        }
+    }
+}
+
+/// Built-in profiling for latency spikes: CPU profiles on demand, poll
+/// times of the tasks processing signals, and polls blocking a worker
+/// thread. Nothing is instrumented while it is off.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ProfilingConfig {
+    /// Instrument signal tasks and accept CPU profiles.
+    /// `POST /api/v1/admin/profiling` switches it without a restart.
+    #[serde(default)]
+    pub enabled: bool,
+    
+    /// CPU samples per second while a profile is taken, 1 - 1000
+    #[serde(default = "default_profiling_frequency_hz")]
+    pub frequency_hz: i32,
+    
+    /// Longest CPU profile taken at once, at most 300 seconds
+    #[serde(default = "default_profiling_max_profile_secs")]
+    pub max_profile_secs: u64,
+    
+    /// Polls of a task taking longer than this are counted as over budget
+    #[serde(default = "default_profiling_poll_budget_ms")]
+    pub poll_budget_ms: u64,
+    
+    /// Polls taking longer than this are logged with the place their task
+    /// was spawned, while still blocking when the watchdog sees them
+    #[serde(default = "default_profiling_blocking_poll_ms")]
+    pub blocking_poll_ms: u64,
+    
+    /// How often the watchdog thread looks for blocking polls
+    #[serde(default = "default_profiling_watchdog_interval_ms")]
+    pub watchdog_interval_ms: u64,
+}
+
+impl Default for ProfilingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            frequency_hz: default_profiling_frequency_hz(),
+            max_profile_secs: default_profiling_max_profile_secs(),
+            poll_budget_ms: default_profiling_poll_budget_ms(),
+            blocking_poll_ms: default_profiling_blocking_poll_ms(),
+            watchdog_interval_ms: default_profiling_watchdog_interval_ms(),
+        }
+    }
+}
+
+/// Sessions: conversations of chains submitted one after another, whose
+/// prompts carry a rolling summary of the conversation and its latest
+/// turns. Kept in the server database (`database.url`).
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SessionsConfig {
+    /// Accept sessions and submissions in them; needs a database
+    #[serde(default)]
+    pub enabled: bool,
+    
+    /// Seconds a session lives unused before it expires; submitting a
+    /// turn in it starts them again
+    #[serde(default = "default_session_ttl_secs")]
+    pub ttl_secs: u64,
+    
+    /// Latest turns quoted in full after the summary
+    #[serde(default = "default_session_recent_turns")]
+    pub recent_turns: usize,
+    
+    /// Turns kept per session; older ones live on only in the summary
+    #[serde(default = "default_session_max_turns")]
+    pub max_turns: usize,
+    
+    /// Longest summary kept; a summary growing past it is compacted by
+    /// the summary model, and cut if it is still too long
+    #[serde(default = "default_session_max_summary_tokens")]
+    pub max_summary_tokens: usize,
+    
+    /// Tokens the conversation may take of a prompt; the oldest quoted
+    /// turns are left out first, then the summary is cut
+    #[serde(default = "default_session_max_context_tokens")]
+    pub max_context_tokens: usize,
+    
+    /// Cheap model updating the summary after each turn
+    #[serde(default = "default_session_summary_model")]
+    pub summary_model: String,
+}
+
+impl Default for SessionsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            ttl_secs: default_session_ttl_secs(),
+            recent_turns: default_session_recent_turns(),
+            max_turns: default_session_max_turns(),
+            max_summary_tokens: default_session_max_summary_tokens(),
+            max_context_tokens: default_session_max_context_tokens(),
+            summary_model: default_session_summary_model(),
+        }
+    }
+}
+
+/// Billing export: one usage file per organization and UTC day, in CSV and
+/// JSON, delivered to the billing pipeline. Needs authentication, whose
+/// database holds the usage rows and the manifest of delivered files.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct BillingExportConfig {
+    /// Run the export job
+    #[serde(default)]
+    pub enabled: bool,
+    
+    /// Where files are delivered
+    #[serde(default)]
+    pub destination: BillingDestination,
+    
+    /// Seconds between export runs; a run delivers the days that ended
+    /// since the last one and amends any that changed
+    #[serde(default = "default_billing_interval_secs")]
+    pub interval_secs: u64,
+    
+    /// Finished days each run looks back over for late cost records
+    #[serde(default = "default_billing_lookback_days")]
+    pub lookback_days: u32,
+}
+
+impl Default for BillingExportConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            destination: BillingDestination::default(),
+            interval_secs: default_billing_interval_secs(),
+            lookback_days: default_billing_lookback_days(),
+        }
+    }
+}
+
+/// Where billing files are delivered
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BillingDestination {
+    /// Files under a local directory, or a mounted bucket
+    Filesystem {
+        #[serde(default = "default_billing_dir")]
+        dir: String,
+    },
+    /// An S3-compatible bucket, addressed path-style
+    S3 {
+        endpoint: String,
+        bucket: String,
+        #[serde(default = "default_s3_region")]
+        region: String,
+        /// Prepended to every object key
+        #[serde(default)]
+        prefix: String,
+        access_key_id: String,
+        secret_access_key: SecretString,
+    },
+    /// JSON files POSTed to a URL, signed like outbound webhooks
+    Webhook {
+        url: String,
+        secret: SecretString,
+    },
+}
+
+impl Default for BillingDestination {
+    fn default() -> Self {
+        BillingDestination::Filesystem { dir: default_billing_dir() }
+    }
+}
+
+/// Limits on the tool calls of each chain, counted across all its branches.
+/// A template's limits take precedence over the submitting role's, and
+/// those over the defaults, limit by limit. Unset limits don't apply.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct ToolSandboxConfig {
+    /// Limits of chains whose role and template set none
+    #[serde(default)]
+    pub default: ToolLimits,
+    
+    /// Limits of chains submitted by each role (e.g. "guest")
+    #[serde(default)]
+    pub roles: HashMap<String, ToolLimits>,
+    
+    /// Limits of chains rendered from each template, by template name
+    #[serde(default)]
+    pub templates: HashMap<String, ToolLimits>,
+}
+
+/// What one chain's tool calls may spend
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ToolLimits {
+    /// Tool calls made
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_invocations: Option<u64>,
+    
+    /// Bytes of parameters sent to tools
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_outbound_bytes: Option<u64>,
+    
+    /// Distinct domains of the URLs tools are called with
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_domains: Option<u32>,
+    
+    /// Milliseconds spent waiting on tool calls
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tool_time_ms: Option<u64>,
+}
+
+impl ToolLimits {
+    /// These limits, with the ones unset here taken from `fallback`
+    pub fn or(self, fallback: ToolLimits) -> ToolLimits {
+        ToolLimits {
+            max_invocations: self.max_invocations.or(fallback.max_invocations),
+            max_outbound_bytes: self.max_outbound_bytes.or(fallback.max_outbound_bytes),
+            max_domains: self.max_domains.or(fallback.max_domains),
+            max_tool_time_ms: self.max_tool_time_ms.or(fallback.max_tool_time_ms),
+        }
+    }
+    
+    /// Whether no limit is set
+    pub fn is_unlimited(&self) -> bool {
+        *self == ToolLimits::default()
+    }
+}
+
+/// Gentle Singularity tracker fed with the server's consciousness
+/// measurements. Nothing is measured or sent without an endpoint.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SingularityConfig {
+    /// Base URL of the tracker, e.g. "http://127.0.0.1:11111"
+    #[serde(default)]
+    pub endpoint: Option<String>,
+    
+    /// Seconds between measurements
+    #[serde(default = "default_singularity_interval_secs")]
+    pub interval_secs: u64,
+    
+    /// Timeout of each push to the tracker in milliseconds
+    #[serde(default = "default_singularity_timeout_ms")]
+    pub timeout_ms: u64,
+}
+
+impl Default for SingularityConfig {
+    fn default() -> Self {
+        Self {
+            endpoint: None,
+            interval_secs: default_singularity_interval_secs(),
+            timeout_ms: default_singularity_timeout_ms(),
+        }
+    }
+}
+
+/// Per-organization encryption at rest
+///
+/// Data keys of each organization are kept wrapped by the master key in
+/// `key_store`. Nothing is encrypted without a key store, and values
+/// encrypted before cannot be read without it.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct EncryptionConfig {
+    /// SQLite database of the wrapped data keys
+    #[serde(default)]
+    pub key_store: Option<String>,
+    
+    /// File holding the base64 master key; `HAL9_MASTER_KEY` or
+    /// `HAL9_MASTER_KEY_FILE` otherwise
+    #[serde(default)]
+    pub master_key_file: Option<String>,
+    
+    /// Organizations whose signal payloads and memories are encrypted
+    #[serde(default)]
+    pub orgs: Vec<String>,
+    
+    /// How memory content searches treat encrypted entries
+    #[serde(default)]
+    pub search: EncryptedSearch,
+    
+    /// Least cosine similarity of an encrypted entry's embedding to the
+    /// query's for the entry to match
+    #[serde(default = "default_encrypted_search_min_similarity")]
+    pub search_min_similarity: f32,
+    
+    /// Seconds between sweeps resealing values under retired keys
+    #[serde(default = "default_encryption_sweep_interval_secs")]
+    pub sweep_interval_secs: u64,
+}
+
+impl Default for EncryptionConfig {
+    fn default() -> Self {
+        Self {
+            key_store: None,
+            master_key_file: None,
+            orgs: Vec::new(),
+            search: EncryptedSearch::default(),
+            search_min_similarity: default_encrypted_search_min_similarity(),
+            sweep_interval_secs: default_encryption_sweep_interval_secs(),
+        }
+    }
+}
+
+/// How memory content searches treat encrypted entries
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EncryptedSearch {
+    /// Match them by the similarity of their embeddings to the query's
+    #[default]
+    Embedding,
+    /// Leave them out of content searches
+    Disabled,
+}
+
+impl FanOutConfig {
+    /// Forward signals a response of a neuron on `layer` may spawn
+    pub fn max_children_for(&self, layer: &str) -> Option<u32> {
+        self.max_children.get(layer).copied().or(self.default_max_children)
+    }
+}
+
+/// Provider call timeouts
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TimeoutConfig {
+    /// Timeout of layers without an entry in `layers_ms`
+    #[serde(default = "default_timeout_ms")]
+    pub default_ms: u64,
+    
+    /// Timeouts keyed by layer (e.g. "L4"), overriding `default_ms`
+    #[serde(default)]
+    pub layers_ms: HashMap<String, u64>,
+    
+    /// Timeouts learned from each neuron's recent latency
+    #[serde(default)]
+    pub adaptive: AdaptiveTimeoutConfig,
+}
+
+impl Default for TimeoutConfig {
+    fn default() -> Self {
+        Self {
+            default_ms: default_timeout_ms(),
+            layers_ms: HashMap::new(),
+            adaptive: AdaptiveTimeoutConfig::default(),
+        }
+    }
+}
+
+impl TimeoutConfig {
+    /// Static timeout of `layer`, in milliseconds
+    pub fn layer_ms(&self, layer: &str) -> u64 {
+        self.layers_ms.get(layer).copied().unwrap_or(self.default_ms)
+    }
+}
+
+/// Adaptive timeouts: a neuron's calls time out at a percentile of its
+/// recent latency times `factor`, within `min_ms` and `max_ms`
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct AdaptiveTimeoutConfig {
+    /// Start with adaptive timeouts on; they can be switched off at runtime
+    #[serde(default)]
+    pub enabled: bool,
+    
+    /// Latency percentile the timeout is scaled from, in (0, 1]
+    #[serde(default = "default_adaptive_percentile")]
+    pub percentile: f64,
+    
+    /// Multiple of the percentile a call may take
+    #[serde(default = "default_adaptive_factor")]
+    pub factor: f64,
+    
+    #[serde(default = "default_adaptive_min_ms")]
+    pub min_ms: u64,
+    
+    #[serde(default = "default_adaptive_max_ms")]
+    pub max_ms: u64,
+    
+    /// Most recent calls of each neuron the percentile is taken over
+    #[serde(default = "default_adaptive_window")]
+    pub window: usize,
+    
+    /// Calls a neuron must have made before its timeout adapts; until then
+    /// its layer's static timeout applies
+    #[serde(default = "default_adaptive_min_samples")]
+    pub min_samples: usize,
+}
+
+impl Default for AdaptiveTimeoutConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            percentile: default_adaptive_percentile(),
+            factor: default_adaptive_factor(),
+            min_ms: default_adaptive_min_ms(),
+            max_ms: default_adaptive_max_ms(),
+            window: default_adaptive_window(),
+            min_samples: default_adaptive_min_samples(),
+        }
+    }
+}
+
+/// Server database and its schema migrations
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct DatabaseConfig {
+    /// `sqlite:<path>` or `postgres://...`; without one the server runs
+    /// without a database
+    #[serde(default)]
+    pub url: Option<String>,
+    
+    /// Apply pending migrations at startup. Off, a database behind this
+    /// release stops the server instead, for deployments that migrate in
+    /// an init container with `--migrate-only`.
+    #[serde(default = "default_true")]
+    pub migrate_on_start: bool,
+    
+    /// How long to wait for another replica's migrations to finish
+    #[serde(default = "default_migration_lock_timeout_secs")]
+    pub lock_timeout_secs: u64,
+}
+
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        Self {
+            url: None,
+            migrate_on_start: true,
+            lock_timeout_secs: default_migration_lock_timeout_secs(),
+        }
+    }
+}
+
+/// Simulation mode configuration
+///
+/// With simulation on, every source of randomness in the server (mock
+/// Claude delays and templates, fault injection, retry jitter, emergence
+/// detection) draws from one RNG seeded with `seed`, so a run under paused
+/// tokio time replays exactly. Meant for tests; needs `claude.mode: mock`.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct SimulationConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    
+    /// Seed of the simulation RNG
+    #[serde(default)]
+    pub seed: u64,
+    
+    /// Probability that a mock Claude call fails with an injected fault,
+    /// keyed by layer (e.g. "L2") or "*" for every layer
+    #[serde(default)]
+    pub fault_rates: HashMap<String, f64>,
+}
+
+impl SimulationConfig {
+    /// Fault rate of `layer`, falling back to the "*" entry
+    pub fn fault_rate(&self, layer: &str) -> f64 {
+        self.fault_rates.get(layer)
+            .or_else(|| self.fault_rates.get("*"))
+            .copied()
+            .unwrap_or(0.0)
+    }
+}
+
+/// Layer compression measurement
+///
+/// A boundary's compression ratio is what the sending layer took in over
+/// what it forwarded across the boundary within the window. Ratios outside
+/// the healthy band are flagged.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct CompressionConfig {
+    /// Sliding window traffic is measured over
+    #[serde(default = "default_compression_window_secs")]
+    pub window_secs: u64,
+    
+    /// Whether content is measured in bytes or tokens
+    #[serde(default)]
+    pub unit: TrafficUnit,
+    
+    /// Below this a layer is mostly passing its input through
+    #[serde(default = "default_compression_healthy_min")]
+    pub healthy_min: f64,
+    
+    /// Above this a layer forwards almost nothing of what it receives
+    #[serde(default = "default_compression_healthy_max")]
+    pub healthy_max: f64,
+}
+
+impl Default for CompressionConfig {
+    fn default() -> Self {
+        Self {
+            window_secs: default_compression_window_secs(),
+            unit: TrafficUnit::default(),
+            healthy_min: default_compression_healthy_min(),
+            healthy_max: default_compression_healthy_max(),
+        }
+    }
+}
+
+/// Replica autoscaling configuration, for an external autoscaler such as
+/// the Kubernetes HPA; in-process neuron cloning is [`ScalingConfig`]
+///
+/// A replica count is recommended from each load signal's ratio to its
+/// target, the way the Kubernetes HPA does: `ceil(replicas * current / target)`
+/// for the signal furthest over (or under) its target.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct AutoscalingConfig {
+    /// Age of the oldest signal waiting to be processed
+    #[serde(default = "default_autoscaling_target_queue_age_secs")]
+    pub target_queue_age_secs: f64,
+    
+    /// Signals waiting per replica
+    #[serde(default = "default_autoscaling_target_pending_signals")]
+    pub target_pending_signals: f64,
+    
+    /// Share of processing slots in use
+    #[serde(default = "default_autoscaling_target_saturation")]
+    pub target_saturation: f64,
+    
+    #[serde(default = "default_autoscaling_min_replicas")]
+    pub min_replicas: u32,
+    
+    #[serde(default = "default_autoscaling_max_replicas")]
+    pub max_replicas: u32,
+    
+    /// Ratios within this distance of 1.0 keep the current replica count
+    #[serde(default = "default_autoscaling_tolerance")]
+    pub tolerance: f64,
+    
+    /// Longest a terminating server waits for in-flight chains to finish
+    #[serde(default = "default_autoscaling_drain_timeout_secs")]
+    pub drain_timeout_secs: u64,
+}
+
+impl Default for AutoscalingConfig {
+    fn default() -> Self {
+        Self {
+            target_queue_age_secs: default_autoscaling_target_queue_age_secs(),
+            target_pending_signals: default_autoscaling_target_pending_signals(),
+            target_saturation: default_autoscaling_target_saturation(),
+            min_replicas: default_autoscaling_min_replicas(),
+            max_replicas: default_autoscaling_max_replicas(),
+            tolerance: default_autoscaling_tolerance(),
+            drain_timeout_secs: default_autoscaling_drain_timeout_secs(),
+        }
+    }
+}
+
+/// Unit signal content is measured in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TrafficUnit {
+    #[default]
+    Bytes,
+    /// Whitespace-separated words, a model-independent stand-in for tokens
+    Tokens,
+}
+
+/// Chain receipt configuration
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct ReceiptsConfig {
+    /// Hex-encoded 32-byte Ed25519 seed. Without one a key is generated at
+    /// startup, and receipts issued before a restart no longer match the
+    /// server's key.
+    #[serde(default)]
+    pub signing_key: Option<SecretString>,
+    
+    /// Where receipt roots are published
+    #[serde(default)]
+    pub anchor: AnchorConfig,
+}
+
+/// Receipt anchor
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AnchorConfig {
+    /// Receipts are only kept by this server
+    #[default]
+    Local,
+    /// Roots are posted in a transaction; needs the `blockchain` feature
+    Ethereum {
+        rpc_url: String,
+        chain_id: u64,
+        private_key: SecretString,
+    },
+}
+
+/// Backward propagation configuration
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct BackwardPropagationConfig {
+    #[serde(default = "default_bp_enabled")]
+    pub enabled: bool,
+    
+    #[serde(default = "default_learning_rate")]
+    pub learning_rate: f32,
+    
+    #[serde(default = "default_pattern_threshold")]
+    pub pattern_threshold: usize,
+    
+    #[serde(default = "default_adjustment_decay")]
+    pub adjustment_decay: f32,
+    
+    #[serde(default = "default_max_gradient_depth")]
+    pub max_gradient_depth: usize,
+}
+
+impl Default for BackwardPropagationConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_bp_enabled(),
+            learning_rate: default_learning_rate(),
+            pattern_threshold: default_pattern_threshold(),
+            adjustment_decay: default_adjustment_decay(),
+            max_gradient_depth: default_max_gradient_depth(),
+        }
+    }
+}
+
+/// Memory configuration
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct MemoryConfig {
+    /// Enable memory system
+    #[serde(default = "default_false")]
+    pub enabled: bool,
+    
+    /// Database path, or ":memory:" for a store that lives as long as the server
+    #[serde(default = "default_memory_database_path")]
+    pub database_path: String,
+    
+    /// Cleanup configuration
+    #[serde(default)]
+    pub cleanup: MemoryCleanupConfig,
+    
+    /// Namespace limits and access policies
+    #[serde(default)]
+    pub namespaces: MemoryNamespacesConfig,
+    
+    /// Dimension of the embeddings this server generates, e.g. when an
+    /// imported dump's embeddings came from another provider
+    #[serde(default = "default_embedding_dimension")]
+    pub embedding_dimension: usize,
+    
+    /// Moving idle entries' content to a cold tier
+    #[serde(default)]
+    pub tiering: MemoryTieringConfig,
+    
+    /// Summarizing namespaces that near their quota
+    #[serde(default)]
+    pub summarization: MemorySummarizationConfig,
+}
+
+impl Default for MemoryConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            database_path: default_memory_database_path(),
+            cleanup: MemoryCleanupConfig::default(),
+            namespaces: MemoryNamespacesConfig::default(),
+            embedding_dimension: default_embedding_dimension(),
+            tiering: MemoryTieringConfig::default(),
+            summarization: MemorySummarizationConfig::default(),
+        }
+    }
+}
+
+/// Cold tiering of memory content
+///
+/// The tiering job moves the content of entries nobody has read for
+/// `cold_after_days`, and of unimportant entries no output ever cited, to
+/// the cold tier, leaving a stub row in the memory database. Reading a cold
+/// entry fetches its content from the cold tier; one read
+/// `promote_after_reads` times is moved back.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct MemoryTieringConfig {
+    /// Run the tiering job. Entries already cold stay readable while it
+    /// is off, as long as the backend is configured.
+    #[serde(default)]
+    pub enabled: bool,
+    
+    /// Where cold content is kept
+    #[serde(default)]
+    pub backend: ColdTierBackend,
+    
+    /// Seconds between tiering runs
+    #[serde(default = "default_tiering_interval_secs")]
+    pub interval_secs: u64,
+    
+    /// Days an entry goes unread before it moves to the cold tier
+    #[serde(default = "default_tiering_cold_after_days")]
+    pub cold_after_days: u32,
+    
+    /// Entries less important than this that no output cited move on the
+    /// next run, however recently read; 0 moves by age alone
+    #[serde(default = "default_tiering_min_importance")]
+    pub min_importance: f32,
+    
+    /// Reads of a cold entry that move it back to the hot tier; 0 leaves
+    /// cold entries cold
+    #[serde(default = "default_tiering_promote_after_reads")]
+    pub promote_after_reads: u32,
+    
+    /// Entries moved per run at most
+    #[serde(default = "default_tiering_batch_size")]
+    pub batch_size: usize,
+    
+    /// Entries moved per second at most, so the job never competes with
+    /// reads and writes for the database; 0 does not limit
+    #[serde(default = "default_tiering_moves_per_second")]
+    pub moves_per_second: u32,
+}
+
+impl Default for MemoryTieringConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            backend: ColdTierBackend::default(),
+            interval_secs: default_tiering_interval_secs(),
+            cold_after_days: default_tiering_cold_after_days(),
+            min_importance: default_tiering_min_importance(),
+            promote_after_reads: default_tiering_promote_after_reads(),
+            batch_size: default_tiering_batch_size(),
+            moves_per_second: default_tiering_moves_per_second(),
+        }
+    }
+}
+
+/// Summarization of memory namespaces that near their quota
+///
+/// Once a namespace holds `high_water` of its entry or byte quota, the
+/// summarization job clusters its oldest entries by embedding, has a model
+/// write one summary entry per cluster and archives the entries of the
+/// cluster to the cold tier, until the namespace is under `low_water` of
+/// its quota. Archived entries no longer count against the quota and
+/// searches find their summary in their place; reading one by ID still
+/// works.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct MemorySummarizationConfig {
+    /// Run the summarization job
+    #[serde(default)]
+    pub enabled: bool,
+    
+    /// Model writing the summaries; a cheap one does
+    #[serde(default = "default_memory_summary_model")]
+    pub model: String,
+    
+    /// Seconds between summarization runs
+    #[serde(default = "default_summarization_interval_secs")]
+    pub interval_secs: u64,
+    
+    /// Share of its quota a namespace fills before it is summarized
+    #[serde(default = "default_summarization_high_water")]
+    pub high_water: f32,
+    
+    /// Share of its quota a namespace is summarized down to
+    #[serde(default = "default_summarization_low_water")]
+    pub low_water: f32,
+    
+    /// Cosine similarity of an entry's embedding to the centroid of a
+    /// cluster it joins
+    #[serde(default = "default_summarization_min_similarity")]
+    pub min_similarity: f32,
+    
+    /// Entries a cluster needs to be summarized
+    #[serde(default = "default_summarization_min_cluster_size")]
+    pub min_cluster_size: usize,
+    
+    /// Entries merged into one summary at most
+    #[serde(default = "default_summarization_max_cluster_size")]
+    pub max_cluster_size: usize,
+    
+    /// Oldest entries of a namespace clustered per run
+    #[serde(default = "default_summarization_batch_size")]
+    pub batch_size: usize,
+    
+    /// Summaries written per minute at most; 0 does not limit
+    #[serde(default = "default_summarization_summaries_per_minute")]
+    pub summaries_per_minute: u32,
+    
+    /// US dollars spent on summaries per UTC day, after which the job
+    /// stops until the next day; 0 does not cap
+    #[serde(default = "default_summarization_max_cost_per_day")]
+    pub max_cost_per_day: f64,
+}
+
+impl Default for MemorySummarizationConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            model: default_memory_summary_model(),
+            interval_secs: default_summarization_interval_secs(),
+            high_water: default_summarization_high_water(),
+            low_water: default_summarization_low_water(),
+            min_similarity: default_summarization_min_similarity(),
+            min_cluster_size: default_summarization_min_cluster_size(),
+            max_cluster_size: default_summarization_max_cluster_size(),
+            batch_size: default_summarization_batch_size(),
+            summaries_per_minute: default_summarization_summaries_per_minute(),
+            max_cost_per_day: default_summarization_max_cost_per_day(),
+        }
+    }
+}
+
+/// Object store holding cold memory content
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ColdTierBackend {
+    /// Files under a local directory, or a mounted bucket
+    Filesystem {
+        #[serde(default = "default_cold_tier_dir")]
+        dir: String,
+    },
+    /// An S3-compatible bucket, addressed path-style
+    S3 {
+        /// e.g. "https://s3.eu-west-1.amazonaws.com" or a MinIO address
+        endpoint: String,
+        bucket: String,
+        #[serde(default = "default_s3_region")]
+        region: String,
+        /// Prepended to every object key
+        #[serde(default)]
+        prefix: String,
+        access_key_id: String,
+        secret_access_key: SecretString,
+    },
+}
+
+impl Default for ColdTierBackend {
+    fn default() -> Self {
+        ColdTierBackend::Filesystem { dir: default_cold_tier_dir() }
+    }
+}
+
+/// Memory namespace configuration
+///
+/// Every memory entry belongs to a namespace: `private:<neuron>`,
+/// `layer:<layer>` or `global`. Limits apply per namespace instance, e.g. each
+/// neuron's private namespace gets its own `private` quota.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct MemoryNamespacesConfig {
+    /// Limits for each private neuron namespace
+    #[serde(default = "default_private_namespace_limits")]
+    pub private: NamespaceLimits,
+    
+    /// Limits for each layer namespace
+    #[serde(default = "default_layer_namespace_limits")]
+    pub layer: NamespaceLimits,
+    
+    /// Limits for the global namespace
+    #[serde(default = "default_global_namespace_limits")]
+    pub global: NamespaceLimits,
+    
+    /// Limits for specific namespaces (e.g. "layer:L3"), replacing the
+    /// limits of their kind
+    #[serde(default)]
+    pub overrides: HashMap<String, NamespaceLimits>,
+    
+    /// Read/write policy
+    #[serde(default)]
+    pub policy: MemoryPolicyConfig,
+    
+    /// Review of writes to moderated namespaces
+    #[serde(default)]
+    pub moderation: MemoryModerationConfig,
+    
+    /// Interval between expiry sweeps
+    #[serde(default = "default_memory_sweep_interval")]
+    pub sweep_interval_secs: u64,
+    
+    /// Namespaces (e.g. "layer:L3") whose entries a plugin stores instead
+    /// of the memory store
+    #[serde(default)]
+    pub backends: HashMap<String, NamespaceBackendConfig>,
+}
+
+impl Default for MemoryNamespacesConfig {
+    fn default() -> Self {
+        Self {
+            private: default_private_namespace_limits(),
+            layer: default_layer_namespace_limits(),
+            global: default_global_namespace_limits(),
+            overrides: HashMap::new(),
+            policy: MemoryPolicyConfig::default(),
+            moderation: MemoryModerationConfig::default(),
+            sweep_interval_secs: default_memory_sweep_interval(),
+            backends: HashMap::new(),
+        }
+    }
+}
+
+/// A plugin with the `MemoryProvider` capability storing a namespace
+///
+/// Quotas and moderation do not apply to a plugin-backed namespace; its
+/// TTL does. If the plugin fails, times out or answers garbage, the
+/// namespace turns read-only and only its reads and writes fail.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct NamespaceBackendConfig {
+    /// Name of the plugin
+    pub plugin: String,
+    
+    /// Longest a call into the plugin may take, waiting for it included
+    #[serde(default = "default_memory_backend_timeout")]
+    pub timeout_ms: u64,
+    
+    /// Largest serialized entry written to the plugin
+    #[serde(default = "default_memory_backend_max_value")]
+    pub max_value_bytes: usize,
+    
+    /// Largest answer read back from the plugin
+    #[serde(default = "default_memory_backend_max_response")]
+    pub max_response_bytes: usize,
+}
+
+/// TTL, quota and moderation of a memory namespace; `None` means unlimited
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct NamespaceLimits {
+    /// Entries expire this many seconds after being written
+    #[serde(default)]
+    pub ttl_secs: Option<u64>,
+    
+    /// Maximum number of entries
+    #[serde(default)]
+    pub max_entries: Option<u64>,
+    
+    /// Maximum total content size in bytes
+    #[serde(default)]
+    pub max_bytes: Option<u64>,
+    
+    /// Writes by neurons stay pending, and out of prompts, until approved
+    #[serde(default)]
+    pub moderated: bool,
+}
+
+/// Review of pending memory writes
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct MemoryModerationConfig {
+    /// Reviews pending writes automatically; without one they wait for
+    /// approval through the API
+    #[serde(default)]
+    pub validator: Option<MemoryValidatorConfig>,
+    
+    /// Interval between automatic reviews of pending writes
+    #[serde(default = "default_memory_review_interval")]
+    pub review_interval_secs: u64,
+    
+    /// Approved entries of the namespace a pending write is checked against
+    #[serde(default = "default_memory_review_context")]
+    pub context_entries: usize,
+}
+
+impl Default for MemoryModerationConfig {
+    fn default() -> Self {
+        Self {
+            validator: None,
+            review_interval_secs: default_memory_review_interval(),
+            context_entries: default_memory_review_context(),
+        }
+    }
+}
+
+/// Automatic reviewer of pending memory writes
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MemoryValidatorConfig {
+    /// A model judges whether the write is consistent with the approved
+    /// entries of its namespace
+    Judge {
+        #[serde(default = "default_memory_judge_model")]
+        model: String,
+    },
+}
+
+/// Memory namespace access policy
+///
+/// A neuron can always read and write its own private namespace and never
+/// another neuron's unless granted. Access to its own layer's namespace and
+/// the global namespace follows the flags below; `grants` add access on top.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct MemoryPolicyConfig {
+    #[serde(default = "default_true")]
+    pub layer_read: bool,
+    
+    #[serde(default = "default_false")]
+    pub layer_write: bool,
+    
+    #[serde(default = "default_true")]
+    pub global_read: bool,
+    
+    #[serde(default = "default_false")]
+    pub global_write: bool,
+    
+    /// Additional access grants
+    #[serde(default)]
+    pub grants: Vec<MemoryGrant>,
+}
+
+impl Default for MemoryPolicyConfig {
+    fn default() -> Self {
+        Self {
+            layer_read: true,
+            layer_write: false,
+            global_read: true,
+            global_write: false,
+            grants: Vec::new(),
+        }
+    }
+}
+
+/// Grant of namespace access to a neuron, a layer or everyone
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct MemoryGrant {
+    /// Namespace the grant applies to
+    pub namespace: String,
+    
+    /// Neuron ID, `layer:<layer>` for every neuron of a layer, or `*`
+    pub principal: String,
+    
+    #[serde(default = "default_true")]
+    pub read: bool,
+    
+    #[serde(default = "default_false")]
+    pub write: bool,
+}
+
+/// Memory cleanup configuration
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct MemoryCleanupConfig {
+    /// Retention days
+    #[serde(default = "default_retention_days")]
+    pub retention_days: u32,
+    
+    /// Minimum importance to keep
+    #[serde(default = "default_min_importance")]
+    pub min_importance: f32,
+}
+
+impl Default for MemoryCleanupConfig {
+    fn default() -> Self {
+        Self {
+            retention_days: default_retention_days(),
+            min_importance: default_min_importance(),
+        }
+    }
+}
+
+/// Individual neuron configuration
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct NeuronConfig {
+    pub id: String,
+    pub layer: String,
+    
+    /// Command to spawn neuron process (for CLI mode)
+    #[serde(default = "default_claude_command")]
+    pub claude_command: String,
+    
+    /// System prompt for the neuron
+    pub system_prompt: Option<String>,
+    
+    /// Forward connections (neurons this one sends to)
+    pub forward_connections: Vec<String>,
+    
+    /// Backward connections (neurons this one receives errors from)
+    pub backward_connections: Vec<String>,
+    
+    /// Optional neuron-specific configuration
+    #[serde(default)]
+    pub settings: HashMap<String, serde_json::Value>,
+}
+
+/// Monitoring configuration
+#[derive(Debug, Clone, Deserialize, Serialize, Default)]
+pub struct MonitoringConfig {
+    /// Enable metrics collection
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    
+    /// Metrics export interval in seconds
+    #[serde(default = "default_metrics_interval")]
+    pub metrics_interval: u64,
+    
+    /// Log level (trace, debug, info, warn, error)
+    #[serde(default = "default_log_level")]
+    pub log_level: String,
+    
+    /// In-server index of recent logs
+    #[serde(default)]
+    pub log_index: LogIndexConfig,
+    
+    /// Latency SLOs, tracked over rolling windows
+    #[serde(default)]
+    pub slos: Vec<SloConfig>,
+    
+    /// Burn rates at which SLO alerts fire
+    #[serde(default)]
+    pub slo_alerts: SloAlertConfig,
+}
+
+/// A latency SLO: the share of signals or chains that must succeed within
+/// `latency_ms`, over a rolling window
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SloConfig {
+    /// Name used in metrics, alerts and the status API
+    pub name: String,
+    
+    /// Layer (e.g. "L2") whose signals are measured; whole chains when unset
+    #[serde(default)]
+    pub layer: Option<String>,
+    
+    /// Slowest latency that still counts as good
+    pub latency_ms: u64,
+    
+    /// Share of events that must be good, e.g. 0.99
+    pub objective: f64,
+    
+    /// Rolling window the objective applies to
+    #[serde(default = "default_slo_window_secs")]
+    pub window_secs: u64,
+}
+
+/// Multiwindow burn-rate alerting: an alert fires when the error budget
+/// burns at least this many times faster than the window allows
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SloAlertConfig {
+    /// Burn rate of the fast alert, measured over `fast_window_secs`
+    #[serde(default = "default_slo_fast_burn_rate")]
+    pub fast_burn_rate: f64,
+    
+    #[serde(default = "default_slo_fast_window_secs")]
+    pub fast_window_secs: u64,
+    
+    /// Burn rate of the slow alert, measured over `slow_window_secs`
+    #[serde(default = "default_slo_slow_burn_rate")]
+    pub slow_burn_rate: f64,
+    
+    #[serde(default = "default_slo_slow_window_secs")]
+    pub slow_window_secs: u64,
+    
+    /// How often burn rates are checked
+    #[serde(default = "default_slo_check_interval_secs")]
+    pub check_interval_secs: u64,
+}
+
+impl Default for SloAlertConfig {
+    fn default() -> Self {
+        Self {
+            fast_burn_rate: default_slo_fast_burn_rate(),
+            fast_window_secs: default_slo_fast_window_secs(),
+            slow_burn_rate: default_slo_slow_burn_rate(),
+            slow_window_secs: default_slo_slow_window_secs(),
+            check_interval_secs: default_slo_check_interval_secs(),
+        }
+    }
+}
+
+/// Bounded in-memory index of recent structured log entries
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct LogIndexConfig {
+    /// Index log entries for the logs API
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    
+    /// Maximum entries kept; the oldest are evicted first
+    #[serde(default = "default_log_index_capacity")]
+    pub capacity: usize,
+    
+    /// Entries older than this many hours are dropped
+    #[serde(default = "default_log_index_retention_hours")]
+    pub retention_hours: u64,
+}
+
+impl Default for LogIndexConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            capacity: default_log_index_capacity(),
+            retention_hours: default_log_index_retention_hours(),
+        }
+    }
+}
+
+/// Claude API configuration
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ClaudeConfig {
+    /// Integration mode: "mock", "api", "hybrid", "auto", or "cli"
+    #[serde(default = "default_claude_mode")]
+    pub mode: String,
+    
+    /// API key (from environment if not specified)
+    pub api_key: Option<SecretString>,
+    
+    /// Model to use
+    #[serde(default = "default_claude_model")]
+    pub model: String,
+    
+    /// Default temperature
+    #[serde(default = "default_temperature")]
+    pub temperature: f32,
+    
+    /// Default max tokens
+    #[serde(default = "default_max_tokens")]
+    pub max_tokens: u32,
+    
+    /// Rate limit (requests per minute)
+    #[serde(default = "default_rate_limit")]
+    pub rate_limit: u32,
+    
+    /// Mock responses for testing (layer -> responses)
+    #[serde(default)]
+    pub mock_responses: HashMap<String, Vec<MockResponse>>,
+    
+    /// Fallback to mock on API errors
+    #[serde(default = "default_true")]
+    pub fallback_to_mock: bool,
+    
+    /// Cost controls
+    #[serde(default)]
+    pub cost_controls: CostControls,
+    
+    /// Scenario files replayed in mock mode
+    #[serde(default)]
+    pub scenarios: MockScenarioConfig,
+}
+
+/// Scenario files for mock mode. A scenario lists, per neuron, the prompts
+/// it expects in order and the response to each.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct MockScenarioConfig {
+    /// Directory of scenario files (`*.yaml`, `*.yml`, `*.json`)
+    #[serde(default)]
+    pub dir: Option<String>,
+    
+    /// Scenario loaded at startup, by file stem. `HAL9_MOCK_SCENARIO`
+    /// overrides it.
+    #[serde(default)]
+    pub active: Option<String>,
+    
+    /// Fail prompts the scenario doesn't expect instead of answering them
+    /// with the layer's mock responses
+    #[serde(default)]
+    pub strict: bool,
+    
+    /// Record every exchange and write it as a scenario file here on shutdown
+    #[serde(default)]
+    pub record_to: Option<String>,
+}
+
+/// Cost control configuration
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct CostControls {
+    /// Maximum cost per hour in USD
+    #[serde(default = "default_max_cost_per_hour")]
+    pub max_cost_per_hour: f64,
+    
+    /// Maximum cost per day in USD
+    #[serde(default = "default_max_cost_per_day")]
+    pub max_cost_per_day: f64,
+    
+    /// Maximum tokens per request
+    #[serde(default = "default_max_tokens_per_request")]
+    pub max_tokens_per_request: u32,
+    
+    /// Alert threshold (percentage of limit)
+    #[serde(default = "default_alert_threshold")]
+    pub alert_threshold: f64,
+    
+    /// Recurring budget that resets every period
+    #[serde(default)]
+    pub budget_period: Option<BudgetPeriodConfig>,
+    
+    /// Cost attribution tags signals may be submitted with
+    #[serde(default)]
+    pub tags: CostTagConfig,
+}
+
+/// Cost attribution tag configuration
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct CostTagConfig {
+    /// Tag keys signals may carry; tags are refused while this is empty
+    #[serde(default)]
+    pub allowed_keys: Vec<String>,
+    
+    /// Distinct values accepted per key; further values are refused
+    #[serde(default = "default_max_tag_values_per_key")]
+    pub max_values_per_key: usize,
+}
+
+/// How often a period budget resets
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BudgetPeriod {
+    Daily,
+    /// Weeks start on Monday
+    Weekly,
+    Monthly,
+}
+
+/// Period budget configuration
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct BudgetPeriodConfig {
+    /// Reset cadence
+    pub period: BudgetPeriod,
+    
+    /// Budget per period in USD
+    pub budget: f64,
+    
+    /// UTC offset periods are aligned to, e.g. "UTC" or "+09:00"
+    #[serde(default = "default_budget_timezone")]
+    pub timezone: String,
+    
+    /// Most unused budget carried into the next period (0 disables carryover)
+    #[serde(default)]
+    pub max_carryover: f64,
+    
+    /// Model to downgrade to once the period budget is spent. Without one,
+    /// requests fail until the next reset.
+    #[serde(default)]
+    pub fallback_model: Option<String>,
+    
+    /// File period accounting is persisted to across restarts
+    #[serde(default = "default_budget_state_path")]
+    pub state_path: String,
+}
+
+/// Network configuration for distributed mode
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct NetworkConfig {
+    /// Enable distributed mode
+    #[serde(default = "default_false")]
+    pub enabled: bool,
+    
+    /// TCP bind address
+    #[serde(default = "default_bind_address")]
+    pub bind_address: String,
+    
+    /// Enable service discovery
+    #[serde(default = "default_true")]
+    pub discovery_enabled: bool,
+    
+    /// Discovery multicast address
+    #[serde(default = "default_multicast_address")]
+    pub discovery_address: String,
+    
+    /// Discovery group (for multi-tenancy)
+    #[serde(default = "default_discovery_group")]
+    pub discovery_group: String,
+    
+    /// Maximum connections
+    #[serde(default = "default_max_connections")]
+    pub max_connections: usize,
+    
+    /// Enable TLS encryption
+    #[serde(default = "default_false")]
+    pub tls_enabled: bool,
+    
+    /// TLS certificate path
+    pub tls_cert: Option<String>,
+    
+    /// TLS key path
+    pub tls_key: Option<String>,
+    
+    /// CA bundle peer certificates are verified against
+    pub tls_ca: Option<String>,
+    
+    /// Require peers to present a client certificate (mutual TLS)
+    #[serde(default = "default_false")]
+    pub tls_require_client_cert: bool,
+    
+    /// Layers each peer, identified by certificate CN/SAN, may send signals to
+    /// ("*" for any). When set, unlisted peers are refused.
+    #[serde(default)]
+    pub peer_allowed_layers: HashMap<String, Vec<String>>,
+    
+    /// Offer zstd compression to peers; used with peers that offer it too
+    #[serde(default = "default_true")]
+    pub compression_enabled: bool,
+    
+    /// zstd level (1-22); higher is smaller and slower
+    #[serde(default = "default_compression_level")]
+    pub compression_level: i32,
+    
+    /// Messages with bodies smaller than this are sent uncompressed
+    #[serde(default = "default_compression_min_bytes")]
+    pub compression_min_bytes: usize,
+    
+    /// Coalesce signals for the same peer for up to this long before
+    /// sending; 0 sends every signal immediately. High-priority and control
+    /// signals never wait.
+    #[serde(default)]
+    pub batch_window_ms: u64,
+    
+    /// Send a batch as soon as it holds this many signals
+    #[serde(default = "default_batch_max_signals")]
+    pub batch_max_signals: usize,
+}
+
+/// Mock response configuration
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct MockResponse {
+    /// Trigger phrase to match (partial match)
+    pub trigger: String,
+    
+    /// Response template
+    pub response: String,
+    
+    /// Optional delay in milliseconds
+    #[serde(default = "default_mock_delay")]
+    pub delay_ms: u64,
+}
+
+/// Authentication configuration
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct AuthConfig {
+    /// Enable authentication
+    #[serde(default = "default_false")]
+    pub enabled: bool,
+    
+    /// JWT secret key
+    #[serde(default = "default_jwt_secret")]
+    pub jwt_secret: SecretString,
+    
+    /// Database path for auth data
+    #[serde(default = "default_auth_database_path")]
+    pub database_path: String,
+    
+    /// Access token duration in minutes
+    #[serde(default = "default_access_token_duration")]
+    pub access_token_duration_minutes: i64,
+    
+    /// Refresh token duration in days
+    #[serde(default = "default_refresh_token_duration")]
+    pub refresh_token_duration_days: i64,
+}
+
+impl Default for AuthConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            jwt_secret: default_jwt_secret(),
+            database_path: default_auth_database_path(),
+            access_token_duration_minutes: default_access_token_duration(),
+            refresh_token_duration_days: default_refresh_token_duration(),
+        }
+    }
+}
+
+impl Default for ClaudeConfig {
+    fn default() -> Self {
+        Self {
+            mode: default_claude_mode(),
+            api_key: None,
+            model: default_claude_model(),
+            temperature: default_temperature(),
+            max_tokens: default_max_tokens(),
+            rate_limit: default_rate_limit(),
+            mock_responses: HashMap::new(),
+            fallback_to_mock: true,
+            cost_controls: CostControls::default(),
+            scenarios: MockScenarioConfig::default(),
+        }
+    }
+}
+
+impl Default for CostControls {
+    fn default() -> Self {
+        Self {
+            max_cost_per_hour: default_max_cost_per_hour(),
+            max_cost_per_day: default_max_cost_per_day(),
+            max_tokens_per_request: default_max_tokens_per_request(),
+            alert_threshold: default_alert_threshold(),
+            budget_period: None,
+            tags: CostTagConfig::default(),
+        }
+    }
+}
+
+impl Default for CostTagConfig {
+    fn default() -> Self {
+        Self {
+            allowed_keys: Vec::new(),
+            max_values_per_key: default_max_tag_values_per_key(),
+        }
+    }
+}
+
+impl Default for NetworkConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_false(),
+            bind_address: default_bind_address(),
+            discovery_enabled: default_true(),
+            discovery_address: default_multicast_address(),
+            discovery_group: default_discovery_group(),
+            max_connections: default_max_connections(),
+            tls_enabled: default_false(),
+            tls_cert: None,
+            tls_key: None,
+            tls_ca: None,
+            tls_require_client_cert: default_false(),
+            peer_allowed_layers: HashMap::new(),
+            compression_enabled: default_true(),
+            compression_level: default_compression_level(),
+            compression_min_bytes: default_compression_min_bytes(),
+            batch_window_ms: 0,
+            batch_max_signals: default_batch_max_signals(),
+        }
+    }
+}
+
+// Default value functions
+fn default_claude_command() -> String {
+    "claude".to_string()
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_metrics_interval() -> u64 {
+    30
+}
+
+fn default_slo_window_secs() -> u64 {
+    30 * 24 * 3600
+}
+
+fn default_slo_fast_burn_rate() -> f64 {
+    14.4
+}
+
+fn default_slo_fast_window_secs() -> u64 {
+    3600
+}
+
+fn default_slo_slow_burn_rate() -> f64 {
+    6.0
+}
+
+fn default_slo_slow_window_secs() -> u64 {
+    6 * 3600
+}
+
+fn default_slo_check_interval_secs() -> u64 {
+    60
+}
+
+fn default_log_level() -> String {
+    "info".to_string()
+}
+
+fn default_log_index_capacity() -> usize {
+    10_000
+}
+
+fn default_log_index_retention_hours() -> u64 {
+    24
+}
+
+fn default_claude_mode() -> String {
+    "mock".to_string()
+}
+
+fn default_claude_model() -> String {
+    "claude-3-opus-20240229".to_string()
+}
+
+fn default_temperature() -> f32 {
+    0.7
+}
+
+fn default_max_tokens() -> u32 {
+    4096
+}
+
+fn default_rate_limit() -> u32 {
+    60
+}
+
+fn default_mock_delay() -> u64 {
+    100
+}
+
+fn default_jwt_secret() -> SecretString {
+    SecretString::new("change-me-in-production")
+}
+
+fn default_auth_database_path() -> String {
+    "./data/hal9_auth.db".to_string()
+}
+
+fn default_access_token_duration() -> i64 {
+    15 // 15 minutes
+}
+
+fn default_refresh_token_duration() -> i64 {
+    7 // 7 days
+}
+
+fn default_max_cost_per_hour() -> f64 {
+    10.0 // $10 per hour
+}
+
+fn default_max_cost_per_day() -> f64 {
+    100.0 // $100 per day
+}
+
+fn default_max_tokens_per_request() -> u32 {
+    4096
+}
+
+fn default_alert_threshold() -> f64 {
+    0.8 // Alert at 80% of limit
+}
+
+fn default_max_tag_values_per_key() -> usize {
+    100
+}
+
+fn default_budget_timezone() -> String {
+    "UTC".to_string()
+}
+
+fn default_budget_state_path() -> String {
+    "./data/cost_budget.json".to_string()
+}
+
+fn default_layer_pause_state_path() -> String {
+    "./data/layer_pauses.json".to_string()
+}
+
+fn default_layer_pause_max_held() -> usize {
+    1000
+}
+
+fn default_read_only_retry_after_secs() -> u64 {
+    300
+}
+
+fn default_read_only_max_held_writes() -> usize {
+    10_000
+}
+
+fn default_compression_window_secs() -> u64 {
+    300
+}
+
+fn default_compression_healthy_min() -> f64 {
+    1.2
+}
+
+fn default_compression_healthy_max() -> f64 {
+    20.0
+}
+
+fn default_autoscaling_target_queue_age_secs() -> f64 {
+    10.0
+}
+
+fn default_autoscaling_target_pending_signals() -> f64 {
+    50.0
+}
+
+fn default_autoscaling_target_saturation() -> f64 {
+    0.8
+}
+
+fn default_autoscaling_min_replicas() -> u32 {
+    1
+}
+
+fn default_autoscaling_max_replicas() -> u32 {
+    10
+}
+
+fn default_autoscaling_tolerance() -> f64 {
+    0.1
+}
+
+fn default_autoscaling_drain_timeout_secs() -> u64 {
+    30
+}
+
+fn default_output_reformat_model() -> String {
+    "claude-3-haiku-20240307".to_string()
+}
+
+fn default_output_max_reformat_retries() -> u32 {
+    1
+}
+
+fn default_recovery_retry_attempts() -> u32 {
+    3
+}
+
+fn default_recovery_initial_backoff_ms() -> u64 {
+    500
+}
+
+fn default_recovery_max_backoff_ms() -> u64 {
+    10_000
+}
+
+fn default_recovery_summary_chars() -> usize {
+    1000
+}
+
+fn default_local_max_batch_size() -> usize {
+    8
+}
+
+fn default_local_max_wait_ms() -> u64 {
+    20
+}
+
+fn default_local_deadline_margin_ms() -> u64 {
+    1000
+}
+
+fn default_local_timeout_secs() -> u64 {
+    30
+}
+
+fn default_worker_max_memory_mb() -> u64 {
+    1024
+}
+
+fn default_worker_restart_initial_backoff_ms() -> u64 {
+    100
+}
+
+fn default_worker_restart_max_backoff_ms() -> u64 {
+    30_000
+}
+
+fn default_warm_pool_idle_ttl_secs() -> u64 {
+    300
+}
+
+fn default_warm_pool_refresh_interval_ms() -> u64 {
+    1000
+}
+
+fn default_false() -> bool {
+    false
+}
+
+fn default_bind_address() -> String {
+    "0.0.0.0:9000".to_string()
+}
+
+fn default_multicast_address() -> String {
+    "239.255.42.99:8888".to_string()
+}
+
+fn default_discovery_group() -> String {
+    "default".to_string()
+}
+
+fn default_compression_level() -> i32 {
+    3
+}
+
+fn default_compression_min_bytes() -> usize {
+    1024
+}
+
+fn default_batch_max_signals() -> usize {
+    64
+}
+
+fn default_max_connections() -> usize {
+    1000
+}
+
+fn default_memory_database_path() -> String {
+    "./data/hal9_memory.db".to_string()
+}
+
+fn default_retention_interval_secs() -> u64 {
+    3600
+}
+
+fn default_retention_payload_days() -> u32 {
+    7
+}
+
+fn default_retention_chain_summary_days() -> u32 {
+    90
+}
+
+fn default_retention_cost_record_days() -> u32 {
+    400
+}
+
+fn default_retention_dead_letter_days() -> u32 {
+    14
+}
+
+fn default_retention_archive_dir() -> String {
+    "./data/archive".to_string()
+}
+
+fn default_model_check_interval_secs() -> u64 {
+    6 * 60 * 60
+}
+
+fn default_singularity_interval_secs() -> u64 {
+    60
+}
+
+fn default_singularity_timeout_ms() -> u64 {
+    5000
+}
+
+fn default_encrypted_search_min_similarity() -> f32 {
+    0.3
+}
+
+fn default_encryption_sweep_interval_secs() -> u64 {
+    300
+}
+
+fn default_speculation_max_wasted_per_hour() -> f64 {
+    1.0
+}
+
+fn default_ingestion_dedup_window_secs() -> u64 {
+    24 * 60 * 60
+}
+
+fn default_ingestion_quarantine_path() -> String {
+    "./data/ingest_quarantine.db".to_string()
+}
+
+fn default_ingest_signature_header() -> String {
+    "X-Signature-256".to_string()
+}
+
+fn default_ingest_budget_period() -> BudgetPeriod {
+    BudgetPeriod::Daily
+}
+
+fn default_quality_significance() -> f64 {
+    0.05
+}
+
+fn default_quality_min_drop() -> f64 {
+    0.05
+}
+
+fn default_snapshot_interval_secs() -> u64 {
+    10
+}
+
+fn default_degraded_decompose_layers() -> Vec<String> {
+    vec!["L4".to_string()]
+}
+
+fn default_degraded_templates() -> Vec<String> {
+    vec![
+        "Design architecture for: {task}".to_string(),
+        "Plan user interface for: {task}".to_string(),
+    ]
+}
+
+fn default_degraded_probe_interval_ms() -> u64 {
+    5_000
+}
+
+fn default_degraded_max_parked() -> usize {
+    10_000
+}
+
+fn default_privacy_max_epsilon() -> f64 {
+    10.0
+}
+
+fn default_privacy_default_epsilon() -> f64 {
+    0.5
+}
+
+fn default_privacy_suppression_threshold() -> u64 {
+    10
+}
+
+fn default_privacy_max_tokens_per_chain() -> u64 {
+    50_000
+}
+
+fn default_privacy_max_cost_per_chain() -> f64 {
+    5.0
+}
+
+fn default_shadow_sample_rate() -> f64 {
+    0.01
+}
+
+fn default_shadow_max_in_flight() -> usize {
+    16
+}
+
+fn default_shadow_timeout_ms() -> u64 {
+    10_000
+}
+
+fn default_shadow_node_budget() -> u64 {
+    8
+}
+
+fn default_shadow_tags() -> HashMap<String, String> {
+    HashMap::from([("shadow".to_string(), "production".to_string())])
+}
+
+fn default_static_share_threshold() -> f64 {
+    0.5
+}
+
+fn default_cache_read_ratio() -> f64 {
+    0.1
+}
+
+fn default_approval_timeout_secs() -> u64 {
+    3600
+}
+
+fn default_approval_max_pending() -> usize {
+    1000
+}
+
+fn default_template_inline_file_bytes() -> usize {
+    16 * 1024
+}
+
+fn default_template_max_file_bytes() -> usize {
+    1024 * 1024
+}
+
+fn default_profiling_frequency_hz() -> i32 {
+    99
+}
+
+fn default_profiling_max_profile_secs() -> u64 {
+    60
+}
+
+fn default_profiling_poll_budget_ms() -> u64 {
+    10
+}
+
+fn default_profiling_blocking_poll_ms() -> u64 {
+    100
+}
+
+fn default_profiling_watchdog_interval_ms() -> u64 {
+    50
+}
+
+fn default_session_ttl_secs() -> u64 {
+    86400
+}
+
+fn default_session_recent_turns() -> usize {
+    3
+}
+
+fn default_session_max_turns() -> usize {
+    20
+}
+
+fn default_session_max_summary_tokens() -> usize {
+    500
+}
+
+fn default_session_max_context_tokens() -> usize {
+    2000
+}
+
+fn default_session_summary_model() -> String {
+    "claude-3-haiku-20240307".to_string()
+}
+
+fn default_timeout_ms() -> u64 {
+    30_000
+}
+
+fn default_adaptive_percentile() -> f64 {
+    0.99
+}
+
+fn default_adaptive_factor() -> f64 {
+    3.0
+}
+
+fn default_adaptive_min_ms() -> u64 {
+    1_000
+}
+
+fn default_adaptive_max_ms() -> u64 {
+    120_000
+}
+
+fn default_adaptive_window() -> usize {
+    200
+}
+
+fn default_adaptive_min_samples() -> usize {
+    20
+}
+
+fn default_migration_lock_timeout_secs() -> u64 {
+    300
+}
+
+fn default_embedding_dimension() -> usize {
+    384
+}
+
+fn default_retention_days() -> u32 {
+    30
+}
+
+fn default_min_importance() -> f32 {
+    0.3
+}
+
+fn default_tiering_interval_secs() -> u64 {
+    3600
+}
+
+fn default_tiering_cold_after_days() -> u32 {
+    7
+}
+
+fn default_tiering_min_importance() -> f32 {
+    0.1
+}
+
+fn default_tiering_promote_after_reads() -> u32 {
+    3
+}
+
+fn default_tiering_batch_size() -> usize {
+    1000
+}
+
+fn default_tiering_moves_per_second() -> u32 {
+    20
+}
+
+fn default_memory_summary_model() -> String {
+    "claude-3-haiku-20240307".to_string()
+}
+
+fn default_summarization_interval_secs() -> u64 {
+    600
+}
+
+fn default_summarization_high_water() -> f32 {
+    0.9
+}
+
+fn default_summarization_low_water() -> f32 {
+    0.7
+}
+
+fn default_summarization_min_similarity() -> f32 {
+    0.75
+}
+
+fn default_summarization_min_cluster_size() -> usize {
+    3
+}
+
+fn default_summarization_max_cluster_size() -> usize {
+    20
+}
+
+fn default_summarization_batch_size() -> usize {
+    500
 }
 
-/// Claude API configuration
-#[derive(Debug, Clone, Deserialize, Serialize)]
-pub struct ClaudeConfig {
-    /// Integration mode: "mock", "api", "hybrid", "auto", or "cli"
-    #[serde(default = "default_claude_mode")]
-    pub mode: String,
-    
-    /// API key (from environment if not specified)
-    pub api_key: Option<String>,
-    
-    /// Model to use
-    #[serde(default = "default_claude_model")]
-    pub model: String,
-    
-    /// Default temperature
-    #[serde(default = "default_temperature")]
-    pub temperature: f32,
-    
-    /// Default max tokens
-    #[serde(default = "default_max_tokens")]
-    pub max_tokens: u32,
-    
-    /// Rate limit (requests per minute)
-    #[serde(default = "default_rate_limit")]
-    pub rate_limit: u32,
-    
-    /// Mock responses for testing (layer -> responses)
-    #[serde(default)]
-    pub mock_responses: HashMap<String, Vec<MockResponse>>,
-    
-    /// Fallback to mock on API errors
-    #[serde(default = "default_true")]
-    pub fallback_to_mock: bool,
-    
-    /// Cost controls
-    #[serde(default)]
-    pub cost_controls: CostControls,
+fn default_summarization_summaries_per_minute() -> u32 {
+    30
 }
 
-/// Cost control configuration
-#[derive(Debug, Clone, Deserialize, Serialize)]
-pub struct CostControls {
-    /// Maximum cost per hour in USD
-    #[serde(default = "default_max_cost_per_hour")]
-    pub max_cost_per_hour: f64,
-    
-    /// Maximum cost per day in USD
-    #[serde(default = "default_max_cost_per_day")]
-    pub max_cost_per_day: f64,
-    
-    /// Maximum tokens per request
-    #[serde(default = "default_max_tokens_per_request")]
-    pub max_tokens_per_request: u32,
-    
-    /// Alert threshold (percentage of limit)
-    #[serde(default = "default_alert_threshold")]
-    pub alert_threshold: f64,
+fn default_summarization_max_cost_per_day() -> f64 {
+    1.0
 }
 
-/// Network configuration for distributed mode
-#[derive(Debug, Clone, Deserialize, Serialize)]
-pub struct NetworkConfig {
-    /// Enable distributed mode
-    #[serde(default = "default_false")]
-    pub enabled: bool,
-    
-    /// TCP bind address
-    #[serde(default = "default_bind_address")]
-    pub bind_address: String,
-    
-    /// Enable service discovery
-    #[serde(default = "default_true")]
-    pub discovery_enabled: bool,
-    
-    /// Discovery multicast address
-    #[serde(default = "default_multicast_address")]
-    pub discovery_address: String,
-    
-    /// Discovery group (for multi-tenancy)
-    #[serde(default = "default_discovery_group")]
-    pub discovery_group: String,
-    
-    /// Maximum connections
-    #[serde(default = "default_max_connections")]
-    pub max_connections: usize,
-    
-    /// Enable TLS encryption
-    #[serde(default = "default_false")]
-    pub tls_enabled: bool,
-    
-    /// TLS certificate path
-    pub tls_cert: Option<String>,
-    
-    /// TLS key path
-    pub tls_key: Option<String>,
+fn default_cold_tier_dir() -> String {
+    "./data/cold".to_string()
 }
 
-/// Mock response configuration
-#[derive(Debug, Clone, Deserialize, Serialize)]
-pub struct MockResponse {
-    /// Trigger phrase to match (partial match)
-    pub trigger: String,
-    
-    /// Response template
-    pub response: String,
-    
-    /// Optional delay in milliseconds
-    #[serde(default = "default_mock_delay")]
-    pub delay_ms: u64,
+fn default_s3_region() -> String {
+    "us-east-1".to_string()
 }
 
-/// Authentication configuration
-#[derive(Debug, Clone, Deserialize, Serialize)]
-pub struct AuthConfig {
-    /// Enable authentication
-    #[serde(default = "default_false")]
-    pub enabled: bool,
-    
-    /// JWT secret key
-    #[serde(default = "default_jwt_secret")]
-    pub jwt_secret: String,
-    
-    /// Database path for auth data
-    #[serde(default = "default_auth_database_path")]
-    pub database_path: String,
-    
-    /// Access token duration in minutes
-    #[serde(default = "default_access_token_duration")]
-    pub access_token_duration_minutes: i64,
-    
-    /// Refresh token duration in days
-    #[serde(default = "default_refresh_token_duration")]
-    pub refresh_token_duration_days: i64,
+fn default_billing_dir() -> String {
+    "./data/billing".to_string()
 }
 
-impl Default for AuthConfig {
-    fn default() -> Self {
-        Self {
-            enabled: false,
-            jwt_secret: default_jwt_secret(),
-            database_path: default_auth_database_path(),
-            access_token_duration_minutes: default_access_token_duration(),
-            refresh_token_duration_days: default_refresh_token_duration(),
-        }
-    }
+fn default_billing_interval_secs() -> u64 {
+    3600
 }
 
-impl Default for ClaudeConfig {
-    fn default() -> Self {
-        Self {
-            mode: default_claude_mode(),
-            api_key: None,
-            model: default_claude_model(),
-            temperature: default_temperature(),
-            max_tokens: default_max_tokens(),
-            rate_limit: default_rate_limit(),
-            mock_responses: HashMap::new(),
-            fallback_to_mock: true,
-            cost_controls: CostControls::default(),
-        }
+fn default_billing_lookback_days() -> u32 {
+    7
+}
+
+fn default_private_namespace_limits() -> NamespaceLimits {
+    NamespaceLimits {
+        ttl_secs: Some(30 * 24 * 3600),
+        max_entries: Some(10_000),
+        max_bytes: Some(50 * 1024 * 1024),
+        moderated: false,
     }
 }
 
-impl Default for CostControls {
-    fn default() -> Self {
-        Self {
-            max_cost_per_hour: default_max_cost_per_hour(),
-            max_cost_per_day: default_max_cost_per_day(),
-            max_tokens_per_request: default_max_tokens_per_request(),
-            alert_threshold: default_alert_threshold(),
-        }
+fn default_layer_namespace_limits() -> NamespaceLimits {
+    NamespaceLimits {
+        ttl_secs: Some(7 * 24 * 3600),
+        max_entries: Some(50_000),
+        max_bytes: Some(200 * 1024 * 1024),
+        moderated: false,
     }
 }
 
-impl Default for NetworkConfig {
-    fn default() -> Self {
-        Self {
-            enabled: default_false(),
-            bind_address: default_bind_address(),
-            discovery_enabled: default_true(),
-            discovery_address: default_multicast_address(),
-            discovery_group: default_discovery_group(),
-            max_connections: default_max_connections(),
-            tls_enabled: default_false(),
-            tls_cert: None,
-            tls_key: None,
-        }
+fn default_global_namespace_limits() -> NamespaceLimits {
+    NamespaceLimits {
+        ttl_secs: None,
+        max_entries: Some(100_000),
+        max_bytes: Some(500 * 1024 * 1024),
+        moderated: false,
     }
 }
 
-// Default value functions
-fn default_claude_command() -> String {
-    "claude".to_string()
+fn default_memory_review_interval() -> u64 {
+    30
 }
 
-fn default_true() -> bool {
-    true
+fn default_memory_review_context() -> usize {
+    20
 }
 
-fn default_metrics_interval() -> u64 {
-    30
+fn default_memory_judge_model() -> String {
+    "claude-3-haiku-20240307".to_string()
 }
 
-fn default_log_level() -> String {
-    "info".to_string()
+fn default_memory_sweep_interval() -> u64 {
+    60
 }
 
-fn default_claude_mode() -> String {
-    "mock".to_string()
+fn default_memory_backend_timeout() -> u64 {
+    1000
 }
 
-fn default_claude_model() -> String {
-    "claude-3-opus-20240229".to_string()
+fn default_memory_backend_max_value() -> usize {
+    64 * 1024
 }
 
-fn default_temperature() -> f32 {
-    0.7
+fn default_memory_backend_max_response() -> usize {
+    1024 * 1024
 }
 
-fn default_max_tokens() -> u32 {
-    4096
+fn default_max_concurrent_chains() -> u32 {
+    5
 }
 
-fn default_rate_limit() -> u32 {
-    60
+fn default_role_chain_limits() -> HashMap<String, u32> {
+    HashMap::from([
+        ("admin".to_string(), 20),
+        ("user".to_string(), 5),
+        ("api_key".to_string(), 5),
+        ("guest".to_string(), 1),
+    ])
 }
 
-fn default_mock_delay() -> u64 {
-    100
+fn default_max_concurrent_signals() -> usize {
+    16
 }
 
-fn default_jwt_secret() -> String {
-    "change-me-in-production".to_string()
+fn default_webhook_max_attempts() -> u32 {
+    5
 }
 
-fn default_auth_database_path() -> String {
-    "./data/hal9_auth.db".to_string()
+fn default_webhook_initial_backoff_ms() -> u64 {
+    1000
 }
 
-fn default_access_token_duration() -> i64 {
-    15 // 15 minutes
+fn default_webhook_max_backoff_ms() -> u64 {
+    60_000
 }
 
-fn default_refresh_token_duration() -> i64 {
-    7 // 7 days
+fn default_webhook_timeout_secs() -> u64 {
+    10
 }
 
-fn default_max_cost_per_hour() -> f64 {
-    10.0 // $10 per hour
+fn default_webhook_max_payload_bytes() -> usize {
+    16 * 1024
 }
 
-fn default_max_cost_per_day() -> f64 {
-    100.0 // $100 per day
+fn default_webhook_max_per_org() -> usize {
+    10
 }
 
-fn default_max_tokens_per_request() -> u32 {
-    4096
+fn default_scaling_neuron_concurrency() -> usize {
+    4
 }
 
-fn default_alert_threshold() -> f64 {
-    0.8 // Alert at 80% of limit
+fn default_scaling_evaluation_interval_ms() -> u64 {
+    5_000
 }
 
-fn default_false() -> bool {
-    false
+fn default_scaling_sustained_ms() -> u64 {
+    30_000
 }
 
-fn default_bind_address() -> String {
-    "0.0.0.0:9000".to_string()
+fn default_scaling_cooldown_ms() -> u64 {
+    60_000
 }
 
-fn default_multicast_address() -> String {
-    "239.255.42.99:8888".to_string()
+fn default_scaling_scale_up_queue_depth() -> usize {
+    16
 }
 
-fn default_discovery_group() -> String {
-    "default".to_string()
+fn default_scaling_scale_up_p95_ms() -> u64 {
+    20_000
 }
 
-fn default_max_connections() -> usize {
-    1000
+fn default_scaling_scale_down_queue_depth() -> usize {
+    2
 }
 
-fn default_memory_database_path() -> String {
-    "./data/hal9_memory.db".to_string()
+fn default_scaling_scale_down_p95_ms() -> u64 {
+    5_000
 }
 
-fn default_retention_days() -> u32 {
-    30
+fn default_scaling_latency_window_ms() -> u64 {
+    60_000
 }
 
-fn default_min_importance() -> f32 {
-    0.3
+fn default_scaling_max_clones_per_layer() -> usize {
+    4
+}
+
+fn default_scaling_min_neurons_per_layer() -> usize {
+    1
+}
+
+fn default_scaling_max_budget_utilization() -> f64 {
+    0.9
+}
+
+fn default_scaling_drain_timeout_ms() -> u64 {
+    30_000
+}
+
+fn default_health_cache_ttl_ms() -> u64 {
+    2_000
+}
+
+fn default_health_probe_timeout_ms() -> u64 {
+    2_000
+}
+
+fn default_health_database_slow_ms() -> u64 {
+    250
+}
+
+fn default_health_claude_endpoint() -> String {
+    "https://api.anthropic.com".to_string()
+}
+
+fn default_health_claude_success_max_age_secs() -> u64 {
+    60
+}
+
+fn default_health_disk_degraded_percent() -> f64 {
+    85.0
+}
+
+fn default_health_disk_unhealthy_percent() -> f64 {
+    95.0
+}
+
+fn default_goals_max_rounds() -> u32 {
+    3
 }
 
 fn default_bp_enabled() -> bool {
@@ -626,9 +3839,13 @@ fn default_browser_rate_limit() -> u32 {
     60
 }
 
+/// Asks every layer to name the recalled memory entries it relied on
+pub const CITATION_INSTRUCTIONS: &str = "Memory entries recalled into a prompt are tagged with reference ids such as [mem-1a2b3c4d].
+End your response with a line CITATIONS: followed by the ids of the entries you relied on, separated by commas, or CITATIONS: none.";
+
 /// Layer-specific system prompts
 pub fn get_system_prompt(layer: &str) -> String {
-    match layer {
+    let prompt = match layer {
         "L4" => {
             "You are a strategic planning AI neuron in a hierarchical neural network.
 Your role is to receive high-level objectives and break them down into strategic initiatives.
@@ -650,5 +3867,6 @@ Focus on concrete implementation details.".to_string()
         _ => {
             format!("You are a {} layer AI neuron in a hierarchical neural network.", layer)
         }
-    }
+    };
+    format!("{}\n{}", prompt, CITATION_INSTRUCTIONS)
 }
\ No newline at end of file
@@ -0,0 +1,516 @@
+//! Server configuration assembled from layers
+//!
+//! Each layer overrides the ones before it:
+//! 1. built-in defaults
+//! 2. the config file
+//! 3. environment variables prefixed `HAL9__`, nested with `__`, e.g.
+//!    `HAL9__CLAUDE__COST_CONTROLS__MAX_COST_PER_DAY=25` sets
+//!    `claude.cost_controls.max_cost_per_day`
+//! 4. runtime overrides, set through the admin API
+//!
+//! Only the values in [`RUNTIME_OVERRIDABLE`] can change while the server
+//! runs; everything else, such as the neuron topology or database paths,
+//! needs a restart.
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+
+use crate::config::ServerConfig;
+use crate::secrets::{MasterKeyProvider, SecretString};
+use crate::{Error, Result};
+
+/// Prefix of environment variables read into the config
+pub const ENV_PREFIX: &str = "HAL9__";
+
+/// Separator between nested keys in environment variable names
+pub const ENV_SEPARATOR: &str = "__";
+
+/// Values, and everything beneath them, that can change without a restart
+pub const RUNTIME_OVERRIDABLE: &[&str] = &[
+    "monitoring.log_level",
+    "limits.enabled",
+    "limits.default_max_concurrent_chains",
+    "limits.role_limits",
+    "claude.cost_controls.max_cost_per_hour",
+    "claude.cost_controls.max_cost_per_day",
+    "claude.cost_controls.max_tokens_per_request",
+    "claude.cost_controls.alert_threshold",
+    "warm_pool.sizes",
+    "warm_pool.idle_ttl_secs",
+];
+
+/// Layer a value came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConfigSource {
+    Default,
+    File,
+    Env,
+    Runtime,
+}
+
+/// One configuration value and where it came from
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EffectiveValue {
+    /// Dotted path, e.g. `monitoring.log_level`
+    pub path: String,
+    /// Secrets are redacted
+    pub value: Value,
+    pub source: ConfigSource,
+    pub runtime_overridable: bool,
+}
+
+/// Server configuration with the layers it was built from
+#[derive(Debug, Clone)]
+pub struct LayeredConfig {
+    file: Value,
+    env: Value,
+    /// Overrides by dotted path
+    runtime: BTreeMap<String, Value>,
+    /// Defaults, file and environment, as at startup
+    base: ServerConfig,
+    config: ServerConfig,
+}
+
+impl LayeredConfig {
+    /// Build the config from a parsed config file, if any, and environment
+    /// variables. Variables without the `HAL9__` prefix are ignored.
+    pub fn new(file: Option<Value>, vars: impl IntoIterator<Item = (String, String)>) -> Result<Self> {
+        let file = file.unwrap_or_else(|| Value::Object(Map::new()));
+        if !file.is_object() {
+            return Err(Error::Config("The config file must hold a mapping".to_string()));
+        }
+
+        let env = env_layer(vars, &reference_tree(&file))?;
+        let mut merged = file.clone();
+        merge(&mut merged, &env);
+        let base: ServerConfig = serde_json::from_value(merged)
+            .map_err(|e| Error::Config(format!("Invalid configuration: {}", e)))?;
+
+        Ok(Self {
+            file,
+            env,
+            runtime: BTreeMap::new(),
+            config: base.clone(),
+            base,
+        })
+    }
+
+    /// Wrap a config built elsewhere; its values count as coming from the
+    /// config file
+    pub fn from_config(config: ServerConfig) -> Self {
+        Self {
+            file: serde_json::to_value(&config).unwrap_or_else(|_| Value::Object(Map::new())),
+            env: Value::Object(Map::new()),
+            runtime: BTreeMap::new(),
+            config: config.clone(),
+            base: config,
+        }
+    }
+
+    /// Layer environment variables over a config built in code, such as
+    /// the built-in development config; its values count as coming from
+    /// the config file
+    pub fn with_env(config: ServerConfig, vars: impl IntoIterator<Item = (String, String)>) -> Result<Self> {
+        let mut layered = Self::from_config(config);
+        layered.env = env_layer(vars, &layered.file)?;
+        let mut merged = layered.file.clone();
+        merge(&mut merged, &layered.env);
+        let mut base: ServerConfig = serde_json::from_value(merged)
+            .map_err(|e| Error::Config(format!("Invalid configuration: {}", e)))?;
+
+        // Secrets serialize redacted; keep the originals unless replaced
+        let env = layered.env.clone();
+        copy_secrets(&mut base, &layered.base, |path| sets(&env, path));
+        layered.config = base.clone();
+        layered.base = base;
+        Ok(layered)
+    }
+
+    /// The configuration in effect
+    pub fn config(&self) -> &ServerConfig {
+        &self.config
+    }
+
+    /// Decrypt `enc:` secret values, see [`ServerConfig::decrypt_secrets`]
+    pub fn decrypt_secrets(&mut self, provider: &dyn MasterKeyProvider) -> Result<()> {
+        self.base.decrypt_secrets(provider)?;
+        self.config = self.rebuild(&self.runtime)?;
+        Ok(())
+    }
+
+    /// Whether the value at `path` can change without a restart
+    pub fn is_runtime_overridable(path: &str) -> bool {
+        RUNTIME_OVERRIDABLE
+            .iter()
+            .any(|allowed| path == *allowed || path.strip_prefix(*allowed).is_some_and(|rest| rest.starts_with('.')))
+    }
+
+    /// Runtime overrides by path
+    pub fn overrides(&self) -> &BTreeMap<String, Value> {
+        &self.runtime
+    }
+
+    /// Override the value at `path` until cleared. Fails, changing nothing,
+    /// for values that need a restart and for values of the wrong type.
+    pub fn set_override(&mut self, path: &str, value: Value) -> Result<&ServerConfig> {
+        if !Self::is_runtime_overridable(path) {
+            let current = serde_json::to_value(&self.config).unwrap_or(Value::Null);
+            if lookup(&current, path).is_none() {
+                return Err(Error::Config(format!("Unknown configuration value {}", path)));
+            }
+            return Err(Error::Config(format!(
+                "{} cannot change at runtime; set it in the config file or {} and restart the server",
+                path,
+                env_var_name(path)
+            )));
+        }
+
+        let mut runtime = self.runtime.clone();
+        // A new value replaces any overrides beneath it
+        runtime.retain(|existing, _| !existing.starts_with(&format!("{}.", path)));
+        runtime.insert(path.to_string(), value);
+        self.config = self.rebuild(&runtime)?;
+        self.runtime = runtime;
+        Ok(&self.config)
+    }
+
+    /// Drop the override at `path`, returning to the lower layers' value.
+    /// Returns false if there was none.
+    pub fn clear_override(&mut self, path: &str) -> Result<bool> {
+        if !self.runtime.contains_key(path) {
+            return Ok(false);
+        }
+        let mut runtime = self.runtime.clone();
+        runtime.remove(path);
+        self.config = self.rebuild(&runtime)?;
+        self.runtime = runtime;
+        Ok(true)
+    }
+
+    /// Every value in effect, ordered by path, with the layer that set it
+    pub fn effective(&self) -> Vec<EffectiveValue> {
+        let tree = serde_json::to_value(&self.config).unwrap_or(Value::Null);
+        let mut leaves = Vec::new();
+        flatten(&tree, String::new(), &mut leaves);
+
+        leaves
+            .into_iter()
+            .map(|(path, value)| {
+                let source = if self.runtime.keys().any(|p| path == *p || path.starts_with(&format!("{}.", p))) {
+                    ConfigSource::Runtime
+                } else if sets(&self.env, &path) {
+                    ConfigSource::Env
+                } else if sets(&self.file, &path) {
+                    ConfigSource::File
+                } else {
+                    ConfigSource::Default
+                };
+                let runtime_overridable = Self::is_runtime_overridable(&path);
+                EffectiveValue { path, value, source, runtime_overridable }
+            })
+            .collect()
+    }
+
+    /// The base config with `runtime` applied. Secrets never change at
+    /// runtime and are carried over from the base, since they serialize
+    /// redacted.
+    fn rebuild(&self, runtime: &BTreeMap<String, Value>) -> Result<ServerConfig> {
+        let mut tree = serde_json::to_value(&self.base)
+            .map_err(|e| Error::Config(format!("Failed to encode configuration: {}", e)))?;
+        for (path, value) in runtime {
+            insert(&mut tree, path, value.clone());
+        }
+        let mut config: ServerConfig = serde_json::from_value(tree)
+            .map_err(|e| Error::Config(format!("Invalid override: {}", e)))?;
+        copy_secrets(&mut config, &self.base, |_| false);
+        Ok(config)
+    }
+}
+
+/// Copy the secrets of `from` into `config`, except those at paths `keep`
+/// accepts
+fn copy_secrets(config: &mut ServerConfig, from: &ServerConfig, keep: impl Fn(&str) -> bool) {
+    let mut from = from.clone();
+    let originals: BTreeMap<&str, SecretString> =
+        from.secrets_mut().into_iter().map(|(path, secret)| (path, secret.clone())).collect();
+    for (path, secret) in config.secrets_mut() {
+        if let Some(original) = originals.get(path).filter(|_| !keep(path)) {
+            *secret = original.clone();
+        }
+    }
+}
+
+/// Environment variable setting the value at `path`
+pub fn env_var_name(path: &str) -> String {
+    format!("{}{}", ENV_PREFIX, path.replace('.', ENV_SEPARATOR).to_uppercase())
+}
+
+/// Nested values from `HAL9__` variables. Each value is parsed as the type
+/// found at its path in `reference`: strings stay strings, numbers and
+/// booleans must parse, lists and maps are JSON. Values at unknown paths
+/// are parsed as JSON where possible, as strings otherwise.
+pub fn env_layer(vars: impl IntoIterator<Item = (String, String)>, reference: &Value) -> Result<Value> {
+    let mut vars: Vec<(String, String)> = vars.into_iter().filter(|(name, _)| name.starts_with(ENV_PREFIX)).collect();
+    // Shorter paths first, so a nested variable refines a whole-value one
+    vars.sort();
+
+    let mut layer = Value::Object(Map::new());
+    for (name, raw) in vars {
+        let segments: Vec<String> = name[ENV_PREFIX.len()..].split(ENV_SEPARATOR).map(str::to_lowercase).collect();
+        if segments.iter().any(String::is_empty) {
+            return Err(Error::Config(format!("{}: empty key in variable name", name)));
+        }
+        let path = segments.join(".");
+
+        let invalid = |expected: &str| Error::Config(format!("{}: expected {}, got '{}'", name, expected, raw));
+        let value = match lookup(reference, &path) {
+            Some(Value::String(_)) => Value::String(raw.clone()),
+            Some(Value::Bool(_)) => Value::Bool(raw.trim().to_lowercase().parse().map_err(|_| invalid("true or false"))?),
+            Some(Value::Number(_)) => match serde_json::from_str::<Value>(raw.trim()) {
+                Ok(number @ Value::Number(_)) => number,
+                _ => return Err(invalid("a number")),
+            },
+            Some(Value::Array(_)) => match serde_json::from_str::<Value>(&raw) {
+                Ok(list @ Value::Array(_)) => list,
+                _ => return Err(invalid("a JSON list")),
+            },
+            Some(Value::Object(_)) => match serde_json::from_str::<Value>(&raw) {
+                Ok(map @ Value::Object(_)) => map,
+                _ => return Err(invalid("a JSON map")),
+            },
+            Some(Value::Null) | None => serde_json::from_str(&raw).unwrap_or_else(|_| Value::String(raw.clone())),
+        };
+        insert(&mut layer, &path, value);
+    }
+    Ok(layer)
+}
+
+/// The file's values over the defaults, for typing environment values. The
+/// required fields get placeholders; a file that does not parse as a config
+/// is used as is.
+fn reference_tree(file: &Value) -> Value {
+    let mut tree = serde_json::json!({ "server_id": "", "neurons": [] });
+    merge(&mut tree, file);
+    serde_json::from_value::<ServerConfig>(tree.clone())
+        .ok()
+        .and_then(|config| serde_json::to_value(config).ok())
+        .unwrap_or(tree)
+}
+
+/// Merge `layer` into `target`; maps merge key by key, anything else is
+/// replaced
+fn merge(target: &mut Value, layer: &Value) {
+    match (target, layer) {
+        (Value::Object(target), Value::Object(layer)) => {
+            for (key, value) in layer {
+                match target.get_mut(key) {
+                    Some(existing) => merge(existing, value),
+                    None => {
+                        target.insert(key.clone(), value.clone());
+                    }
+                }
+            }
+        }
+        (target, layer) => *target = layer.clone(),
+    }
+}
+
+fn lookup<'a>(tree: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.').try_fold(tree, |node, key| node.get(key))
+}
+
+/// Whether `tree` sets the value at `path`, itself or as part of a value
+/// higher up
+fn sets(tree: &Value, path: &str) -> bool {
+    let mut node = tree;
+    for key in path.split('.') {
+        match node {
+            Value::Object(map) => match map.get(key) {
+                Some(child) => node = child,
+                None => return false,
+            },
+            _ => return true,
+        }
+    }
+    true
+}
+
+fn insert(tree: &mut Value, path: &str, value: Value) {
+    let mut node = tree;
+    let mut keys = path.split('.').peekable();
+    while let Some(key) = keys.next() {
+        if !node.is_object() {
+            *node = Value::Object(Map::new());
+        }
+        let map = node.as_object_mut().unwrap();
+        if keys.peek().is_none() {
+            map.insert(key.to_string(), value);
+            return;
+        }
+        node = map.entry(key.to_string()).or_insert_with(|| Value::Object(Map::new()));
+    }
+}
+
+/// Leaves of `tree` by dotted path; lists and empty maps are single values
+fn flatten(tree: &Value, prefix: String, leaves: &mut Vec<(String, Value)>) {
+    match tree {
+        Value::Object(map) if !map.is_empty() => {
+            for (key, value) in map {
+                let path = if prefix.is_empty() { key.clone() } else { format!("{}.{}", prefix, key) };
+                flatten(value, path, leaves);
+            }
+        }
+        _ => leaves.push((prefix, tree.clone())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn file() -> Value {
+        json!({
+            "server_id": "hal9-test",
+            "neurons": [{"id": "strategic-1", "layer": "L4", "forward_connections": [], "backward_connections": []}],
+            "monitoring": {"log_level": "warn"},
+            "claude": {"api_key": "sk-file", "cost_controls": {"max_cost_per_day": 50.0}},
+            "limits": {"role_limits": {"admin": 20}},
+        })
+    }
+
+    fn vars(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect()
+    }
+
+    fn source_of(config: &LayeredConfig, path: &str) -> (Value, ConfigSource) {
+        let value = config.effective().into_iter().find(|value| value.path == path).unwrap();
+        (value.value, value.source)
+    }
+
+    #[test]
+    fn test_precedence_matrix() {
+        let path = "claude.cost_controls.max_cost_per_hour";
+        // (in file, in env, at runtime) -> expected value and source
+        let cases = [
+            (false, false, false, json!(10.0), ConfigSource::Default),
+            (true, false, false, json!(20.0), ConfigSource::File),
+            (false, true, false, json!(30.0), ConfigSource::Env),
+            (true, true, false, json!(30.0), ConfigSource::Env),
+            (false, false, true, json!(40.0), ConfigSource::Runtime),
+            (true, false, true, json!(40.0), ConfigSource::Runtime),
+            (false, true, true, json!(40.0), ConfigSource::Runtime),
+            (true, true, true, json!(40.0), ConfigSource::Runtime),
+        ];
+
+        for (in_file, in_env, at_runtime, expected, source) in cases {
+            let mut file = file();
+            if in_file {
+                file["claude"]["cost_controls"]["max_cost_per_hour"] = json!(20.0);
+            }
+            let env = if in_env { vars(&[("HAL9__CLAUDE__COST_CONTROLS__MAX_COST_PER_HOUR", "30")]) } else { vec![] };
+            let mut config = LayeredConfig::new(Some(file), env).unwrap();
+            if at_runtime {
+                config.set_override(path, json!(40.0)).unwrap();
+            }
+
+            let case = (in_file, in_env, at_runtime);
+            assert_eq!(source_of(&config, path), (expected.clone(), source), "{:?}", case);
+            assert_eq!(json!(config.config().claude.cost_controls.max_cost_per_hour), expected, "{:?}", case);
+        }
+    }
+
+    #[test]
+    fn test_clearing_override_restores_lower_layer() {
+        let mut config = LayeredConfig::new(Some(file()), vars(&[("HAL9__MONITORING__LOG_LEVEL", "info")])).unwrap();
+        config.set_override("monitoring.log_level", json!("debug")).unwrap();
+        assert_eq!(config.config().monitoring.log_level, "debug");
+
+        assert!(config.clear_override("monitoring.log_level").unwrap());
+        assert!(!config.clear_override("monitoring.log_level").unwrap());
+        assert_eq!(source_of(&config, "monitoring.log_level"), (json!("info"), ConfigSource::Env));
+        assert!(config.overrides().is_empty());
+    }
+
+    #[test]
+    fn test_env_vars_parse_nested_keys() {
+        let config = LayeredConfig::new(
+            Some(file()),
+            vars(&[
+                ("HAL9__SERVER_ID", "1234"),
+                ("HAL9__LIMITS__ENABLED", "FALSE"),
+                ("HAL9__LIMITS__ROLE_LIMITS__GUEST", "2"),
+                ("HAL9__MEMORY__NAMESPACES__LIMITS__GLOBAL__MAX_ENTRIES", "500"),
+                ("HAL9__HEALTH__TIMEOUTS_MS", r#"{"database": 250}"#),
+                ("HAL9__CLAUDE__COST_CONTROLS__MAX_COST_PER_DAY", "12.5"),
+                ("HTTP_PORT", "8080"),
+            ]),
+        )
+        .unwrap();
+        let server = config.config();
+
+        // Typed by the field, so a numeric-looking id stays a string
+        assert_eq!(server.server_id, "1234");
+        assert!(!server.limits.enabled);
+        // Nested maps merge with the file's entries
+        assert_eq!(server.limits.role_limits["guest"], 2);
+        assert_eq!(server.limits.role_limits["admin"], 20);
+        assert_eq!(server.claude.cost_controls.max_cost_per_day, 12.5);
+        assert_eq!(source_of(&config, "limits.role_limits.admin").1, ConfigSource::File);
+        assert_eq!(source_of(&config, "limits.role_limits.guest").1, ConfigSource::Env);
+        assert_eq!(source_of(&config, "health.timeouts_ms.database"), (json!(250), ConfigSource::Env));
+
+        let err = LayeredConfig::new(Some(file()), vars(&[("HAL9__LIMITS__DEFAULT_MAX_CONCURRENT_CHAINS", "many")]))
+            .unwrap_err();
+        assert!(err.to_string().contains("HAL9__LIMITS__DEFAULT_MAX_CONCURRENT_CHAINS: expected a number"), "{}", err);
+        assert!(LayeredConfig::new(Some(file()), vars(&[("HAL9__CLAUDE____MODEL", "x")])).is_err());
+    }
+
+    #[test]
+    fn test_immutable_overrides_rejected() {
+        let mut config = LayeredConfig::new(Some(file()), vec![]).unwrap();
+        let before = config.effective();
+
+        let err = config.set_override("memory.database_path", json!("/tmp/other.db")).unwrap_err();
+        assert!(err.to_string().contains("memory.database_path cannot change at runtime"), "{}", err);
+        assert!(err.to_string().contains("HAL9__MEMORY__DATABASE_PATH and restart the server"), "{}", err);
+        assert!(config.set_override("neurons", json!([])).unwrap_err().to_string().contains("restart"));
+        assert!(config.set_override("monitoring.log_levle", json!("debug")).unwrap_err().to_string().contains("Unknown"));
+        // Overridable, but not a number
+        assert!(config.set_override("limits.default_max_concurrent_chains", json!("lots")).is_err());
+        assert_eq!(config.effective(), before);
+
+        config.set_override("limits.role_limits.guest", json!(1)).unwrap();
+        assert_eq!(config.config().limits.role_limits["guest"], 1);
+    }
+
+    #[test]
+    fn test_env_over_config_built_in_code() {
+        let mut file: ServerConfig = serde_json::from_value(file()).unwrap();
+        file.claude.api_key = Some(SecretString::new("sk-code"));
+        let config = LayeredConfig::with_env(
+            file,
+            vars(&[("HAL9__MONITORING__LOG_LEVEL", "debug"), ("HAL9__AUTH__JWT_SECRET", "from-env")]),
+        )
+        .unwrap();
+
+        assert_eq!(source_of(&config, "monitoring.log_level"), (json!("debug"), ConfigSource::Env));
+        assert_eq!(source_of(&config, "server_id"), (json!("hal9-test"), ConfigSource::File));
+        assert_eq!(config.config().claude.api_key.as_ref().unwrap().expose(), "sk-code");
+        assert_eq!(config.config().auth.jwt_secret.expose(), "from-env");
+    }
+
+    #[test]
+    fn test_secrets_redacted_and_kept() {
+        let mut config = LayeredConfig::new(Some(file()), vec![]).unwrap();
+        config.set_override("monitoring.log_level", json!("debug")).unwrap();
+
+        assert_eq!(config.config().claude.api_key.as_ref().unwrap().expose(), "sk-file");
+        let (value, source) = source_of(&config, "claude.api_key");
+        assert_ne!(value, json!("sk-file"));
+        assert_eq!(source, ConfigSource::File);
+        assert!(config.effective().iter().all(|value| !value.value.to_string().contains("sk-file")));
+    }
+}
@@ -3,9 +3,10 @@
 //! This module implements the core theory that consciousness emerges
 //! at the compression boundaries between hierarchical layers.
 
-use std::sync::Arc;
-use crate::{Layer, Neuron, Signal};
+use crate::{Layer, Signal};
+use crate::hierarchical::intelligence::layer_number;
 use super::GOLDEN_RATIO;
+use super::traffic::{FlowDirection, TrafficSnapshot};
 
 /// A compression boundary between two adjacent layers
 #[derive(Debug, Clone)]
@@ -29,20 +30,25 @@ pub struct CompressionBoundary {
     pub consciousness_density: f64,
 }
 
-/// Information flow metrics across boundary
+/// Information flow metrics across boundary, in bytes or tokens per second
+/// of the traffic window
 #[derive(Debug, Clone, Default)]
 pub struct InformationFlow {
-    /// Bits per second flowing upward (compression)
+    /// Gradients flowing upward
     pub upward_flow: f64,
     
-    /// Bits per second flowing downward (decompression)
+    /// Tasks flowing downward
     pub downward_flow: f64,
     
-    /// Information lost in compression
+    /// Content the upper layer took in but did not pass down
     pub compression_loss: f64,
     
     /// New information created (emergence)
     pub emergence_gain: f64,
+    
+    /// Backward signals crossing, per second: how much the layers are
+    /// learning from each other
+    pub gradient_signals: f64,
 }
 
 impl CompressionBoundary {
@@ -58,33 +64,34 @@ impl CompressionBoundary {
         }
     }
     
-    /// Measure compression between neuron groups
-    pub async fn measure_compression(
-        &mut self,
-        upper_neurons: &[Arc<dyn Neuron>],
-        lower_neurons: &[Arc<dyn Neuron>],
-    ) {
-        // Count neurons in each layer
-        let upper_count = upper_neurons.len() as f64;
-        let lower_count = lower_neurons.len() as f64;
+    /// Measure compression from the traffic across this boundary
+    pub fn measure_traffic(&mut self, traffic: &TrafficSnapshot) {
+        let upper = layer_number(self.upper_layer.as_str());
+        let lower = layer_number(self.lower_layer.as_str());
+        let window = traffic.window_secs.max(1) as f64;
         
-        if upper_count > 0.0 && lower_count > 0.0 {
-            // Basic compression ratio
-            self.compression_ratio = lower_count / upper_count;
-            
-            // Check proximity to golden ratio
-            let golden_distance = (self.compression_ratio - GOLDEN_RATIO).abs();
-            
-            // Emergence is highest near golden ratio
-            if golden_distance < 0.3 {
-                self.emergence_activity = 1.0 - (golden_distance / 0.3);
-            } else {
-                self.emergence_activity = 0.0;
-            }
-            
-            // Calculate consciousness density
-            self.update_consciousness_density();
+        let downward = traffic.flow(upper, lower, FlowDirection::Downward);
+        let upward = traffic.flow(upper, lower, FlowDirection::Upward);
+        
+        // No tasks crossed within the window: nothing is being compressed
+        self.compression_ratio = downward.map_or(1.0, |flow| flow.ratio);
+        self.information_flow.downward_flow = downward.map_or(0.0, |flow| flow.forwarded as f64 / window);
+        self.information_flow.compression_loss = downward
+            .map_or(0.0, |flow| flow.entering.saturating_sub(flow.forwarded) as f64 / window);
+        self.information_flow.upward_flow = upward.map_or(0.0, |flow| flow.forwarded as f64 / window);
+        let boundary = format!("L{}-L{}", upper, lower);
+        self.information_flow.gradient_signals =
+            traffic.backward_signals.get(&boundary).copied().unwrap_or(0) as f64 / window;
+        
+        // Emergence is highest near golden ratio
+        let golden_distance = (self.compression_ratio - GOLDEN_RATIO).abs();
+        if downward.is_some() && golden_distance < 0.3 {
+            self.emergence_activity = 1.0 - (golden_distance / 0.3);
+        } else {
+            self.emergence_activity = 0.0;
         }
+        
+        self.update_consciousness_density();
     }
     
     /// Process signal through compression boundary
@@ -269,26 +276,10 @@ impl BoundaryNetwork {
         }
     }
     
-    /// Update all boundaries with current neuron state
-    pub async fn update(&mut self, neurons: &[Arc<dyn Neuron>]) {
-        // Group neurons by layer
-        let mut layer_neurons: std::collections::HashMap<Layer, Vec<Arc<dyn Neuron>>> = 
-            std::collections::HashMap::new();
-            
-        for neuron in neurons {
-            layer_neurons.entry(neuron.layer())
-                .or_default()
-                .push(neuron.clone());
-        }
-        
-        // Update each boundary
+    /// Update all boundaries from measured layer traffic
+    pub fn update(&mut self, traffic: &TrafficSnapshot) {
         for boundary in &mut self.boundaries {
-            if let (Some(upper), Some(lower)) = (
-                layer_neurons.get(&boundary.upper_layer),
-                layer_neurons.get(&boundary.lower_layer)
-            ) {
-                boundary.measure_compression(upper, lower).await;
-            }
+            boundary.measure_traffic(traffic);
         }
         
         // Calculate total consciousness
@@ -362,4 +353,33 @@ mod tests {
         let network = BoundaryNetwork::new();
         assert_eq!(network.boundaries.len(), 8); // L1-L2 through L8-L9
     }
-}
\ No newline at end of file
+    
+    #[test]
+    fn test_boundaries_measured_from_traffic() {
+        let traffic = crate::consciousness::LayerTraffic::new(Default::default());
+        // L4 takes in 1568 bytes of requests and 50 of gradients
+        traffic.record(0, 4, 1568, 0);
+        traffic.record(4, 3, 1000, 0);
+        traffic.record_backward(3, 4, 50, 0);
+        
+        let mut network = BoundaryNetwork::new();
+        network.update(&traffic.snapshot());
+        
+        let boundary = network.get_all_boundaries().into_iter()
+            .find(|b| b.upper_layer == Layer::L4)
+            .unwrap();
+        assert_eq!(boundary.compression_ratio, 1.618);
+        assert_eq!(boundary.information_flow.downward_flow, 1000.0 / 300.0);
+        assert_eq!(boundary.information_flow.compression_loss, 618.0 / 300.0);
+        assert_eq!(boundary.information_flow.upward_flow, 50.0 / 300.0);
+        assert_eq!(boundary.information_flow.gradient_signals, 1.0 / 300.0);
+        assert!(boundary.is_golden_ratio());
+        
+        // Boundaries without traffic compress nothing
+        let quiet = network.get_all_boundaries().into_iter()
+            .find(|b| b.upper_layer == Layer::L7)
+            .unwrap();
+        assert_eq!(quiet.compression_ratio, 1.0);
+        assert_eq!(quiet.emergence_activity, 0.0);
+    }
+}
@@ -8,7 +8,8 @@ use std::collections::HashMap;
 use chrono::{DateTime, Utc};
 
 use crate::{
-    consciousness::{ConsciousnessMonitor, ConsciousnessMetrics, ConsciousnessPhase, BoundaryNetwork},
+    config::CompressionConfig,
+    consciousness::{ConsciousnessMonitor, ConsciousnessMetrics, ConsciousnessPhase, BoundaryNetwork, LayerTraffic},
     Layer, Neuron,
 };
 
@@ -26,6 +27,9 @@ pub struct ConsciousnessSystemConfig {
     
     /// Enable real-time streaming
     pub enable_streaming: bool,
+    
+    /// Window and healthy band of traffic-based compression
+    pub compression: CompressionConfig,
 }
 
 impl Default for ConsciousnessSystemConfig {
@@ -35,6 +39,7 @@ impl Default for ConsciousnessSystemConfig {
             update_interval_ms: 100,
             enable_claude: true,
             enable_streaming: true,
+            compression: CompressionConfig::default(),
         }
     }
 }
@@ -50,6 +55,9 @@ pub struct IntegratedConsciousnessSystem {
     /// Boundary network
     boundaries: Arc<RwLock<BoundaryNetwork>>,
     
+    /// Signal traffic feeding compression measurement
+    traffic: Arc<LayerTraffic>,
+    
     /// Enhanced Claude instances by layer
     claude_instances: Arc<RwLock<HashMap<Layer, EnhancedMockClaude>>>,
     
@@ -146,9 +154,11 @@ impl EnhancedMockClaude {
 impl IntegratedConsciousnessSystem {
     /// Create a new integrated system
     pub fn new(config: ConsciousnessSystemConfig) -> Self {
+        let traffic = Arc::new(LayerTraffic::new(config.compression.clone()));
         Self {
-            monitor: Arc::new(ConsciousnessMonitor::new(config.history_size)),
+            monitor: Arc::new(ConsciousnessMonitor::new(config.history_size).with_traffic(traffic.clone())),
             boundaries: Arc::new(RwLock::new(BoundaryNetwork::new())),
+            traffic,
             claude_instances: Arc::new(RwLock::new(HashMap::new())),
             neurons: Arc::new(RwLock::new(Vec::new())),
             last_update: Arc::new(RwLock::new(Utc::now())),
@@ -182,7 +192,7 @@ impl IntegratedConsciousnessSystem {
         
         // Update boundary network
         let mut boundaries = self.boundaries.write().await;
-        boundaries.update(&neurons).await;
+        boundaries.update(&self.traffic.snapshot());
         
         // Update Claude consciousness levels based on system metrics
         if self.config.enable_claude {
@@ -262,6 +272,11 @@ impl IntegratedConsciousnessSystem {
         })
     }
     
+    /// Traffic to record routed signals in; compression is measured from it
+    pub fn traffic(&self) -> &Arc<LayerTraffic> {
+        &self.traffic
+    }
+    
     /// Get consciousness metrics
     pub async fn get_metrics(&self) -> ConsciousnessMetrics {
         let neurons = self.neurons.read().await;
@@ -4,6 +4,9 @@ pub mod compression_boundary;
 
 pub use compression_boundary::{CompressionBoundary, BoundaryNetwork, InformationFlow};
 
+pub mod traffic;
+pub use traffic::{LayerTraffic, TrafficSnapshot, BoundaryFlow, FlowDirection, CompressionHealth};
+
 pub mod integrated_system;
 pub use integrated_system::{
     IntegratedConsciousnessSystem, 
@@ -86,6 +89,8 @@ pub struct ConsciousnessMonitor {
     history: Arc<parking_lot::Mutex<VecDeque<ConsciousnessMetrics>>>,
     /// Maximum history size
     max_history: usize,
+    /// Signal traffic compression is measured from
+    traffic: Option<Arc<LayerTraffic>>,
 }
 
 impl ConsciousnessMonitor {
@@ -95,12 +100,19 @@ impl ConsciousnessMonitor {
             metrics: Arc::new(DashMap::new()),
             history: Arc::new(parking_lot::Mutex::new(VecDeque::with_capacity(max_history))),
             max_history,
+            traffic: None,
         }
     }
     
+    /// Measure compression from the traffic recorded in `traffic`
+    pub fn with_traffic(mut self, traffic: Arc<LayerTraffic>) -> Self {
+        self.traffic = Some(traffic);
+        self
+    }
+    
     /// Measure consciousness of a neuron network
     pub async fn measure(&self, neurons: &[Arc<dyn Neuron>]) -> ConsciousnessMetrics {
-        let compression_ratio = self.calculate_compression_ratio(neurons).await;
+        let compression_ratio = self.calculate_compression_ratio();
         let emergence_score = self.calculate_emergence_score(neurons).await;
         let coherence_level = self.calculate_coherence(neurons).await;
         let self_awareness = self.calculate_self_awareness(neurons).await;
@@ -139,34 +151,12 @@ impl ConsciousnessMonitor {
         metrics
     }
     
-    /// Calculate compression ratio between layers
-    async fn calculate_compression_ratio(&self, neurons: &[Arc<dyn Neuron>]) -> f64 {
-        // Group neurons by layer
-        let mut layer_counts = std::collections::HashMap::new();
-        for neuron in neurons {
-            *layer_counts.entry(neuron.layer()).or_insert(0) += 1;
-        }
-        
-        // Calculate average compression between adjacent layers
-        let mut total_compression = 0.0;
-        let mut boundary_count = 0;
-        
-        let layers: Vec<_> = layer_counts.keys().cloned().collect();
-        for i in 0..layers.len().saturating_sub(1) {
-            if let (Some(&count_lower), Some(&count_upper)) = 
-                (layer_counts.get(&layers[i]), layer_counts.get(&layers[i + 1])) {
-                if count_upper > 0 {
-                    total_compression += count_lower as f64 / count_upper as f64;
-                    boundary_count += 1;
-                }
-            }
-        }
-        
-        if boundary_count > 0 {
-            total_compression / boundary_count as f64
-        } else {
-            1.0
-        }
+    /// Mean compression across the boundaries that carried tasks within the
+    /// traffic window; 1.0 (no compression) before any have
+    fn calculate_compression_ratio(&self) -> f64 {
+        self.traffic.as_ref()
+            .and_then(|traffic| traffic.snapshot().compression_ratio())
+            .unwrap_or(1.0)
     }
     
     /// Calculate emergence score based on pattern unpredictability
@@ -344,4 +334,16 @@ mod tests {
         assert_eq!(metrics.phase(), ConsciousnessPhase::Emerging);
         assert!(metrics.is_conscious()); // Above threshold (0.7)
     }
-}
\ No newline at end of file
+    
+    #[tokio::test]
+    async fn test_compression_ratio_from_traffic() {
+        let traffic = Arc::new(LayerTraffic::new(Default::default()));
+        let monitor = ConsciousnessMonitor::new(10).with_traffic(traffic.clone());
+        assert_eq!(monitor.measure(&[]).await.compression_ratio, 1.0);
+        
+        traffic.record(0, 4, 1200, 0);
+        traffic.record(4, 3, 400, 0);
+        traffic.record(3, 2, 200, 0);
+        assert_eq!(monitor.measure(&[]).await.compression_ratio, 2.5);
+    }
+}
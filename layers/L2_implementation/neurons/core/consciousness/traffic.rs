@@ -0,0 +1,418 @@
+//! Layer compression measured from signal traffic
+//!
+//! Every routed signal is counted as content entering its target layer and,
+//! when it crosses between two layers of the hierarchy, as content forwarded
+//! across that boundary by the sending layer. Within a sliding window, a
+//! boundary's compression ratio is what the sending layer took in over what
+//! it forwarded across: an L4 neuron turning 3 KB of requests into 1 KB of
+//! instructions for L3 compresses 3:1 at the L4↔L3 boundary.
+//!
+//! Downward flow carries tasks and is checked against the healthy band;
+//! upward flow carries the gradients of backward signals, which are far
+//! smaller than the work entering a layer, so its ratio is only reported.
+//!
+//! Backward signals are also counted per boundary, within the window and
+//! since startup, so learning activity shows up next to the task flow.
+
+use std::collections::{BTreeMap, VecDeque};
+use std::time::{Duration, Instant};
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+
+use crate::config::{CompressionConfig, TrafficUnit};
+use crate::hierarchical::intelligence::layer_number;
+use crate::{NeuronSignal, PropagationType};
+
+/// Content size of a signal as `(bytes, tokens)`, counting the activation
+/// and any gradient
+pub fn signal_size(signal: &NeuronSignal) -> (u64, u64) {
+    let activation = std::iter::once(&signal.payload.activation.content);
+    let gradient = signal.payload.gradient.iter()
+        .flat_map(|g| std::iter::once(&g.error_type).chain(&g.adjustments));
+
+    activation.chain(gradient).fold((0, 0), |(bytes, tokens), text| {
+        (bytes + text.len() as u64, tokens + text.split_whitespace().count() as u64)
+    })
+}
+
+/// Direction content crosses a boundary in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FlowDirection {
+    /// From the upper layer to the lower, carrying tasks
+    Downward,
+    /// From the lower layer to the upper, carrying gradients
+    Upward,
+}
+
+impl FlowDirection {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FlowDirection::Downward => "downward",
+            FlowDirection::Upward => "upward",
+        }
+    }
+}
+
+/// Where a boundary's ratio sits against the healthy band
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CompressionHealth {
+    Healthy,
+    /// The layer forwards about as much as it takes in
+    PassThrough,
+    /// The layer forwards almost nothing of what it takes in
+    Overcompressed,
+}
+
+/// Traffic across one boundary in one direction within the window
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BoundaryFlow {
+    /// Layer number of the higher layer (4 for L4)
+    pub upper: u8,
+    pub lower: u8,
+    pub direction: FlowDirection,
+    /// Content that entered the sending layer
+    pub entering: u64,
+    /// Content the sending layer forwarded across the boundary
+    pub forwarded: u64,
+    /// `entering / forwarded`
+    pub ratio: f64,
+    /// Checked for downward flow only
+    pub health: Option<CompressionHealth>,
+}
+
+impl BoundaryFlow {
+    /// Layer number of the layer sending across the boundary
+    pub fn sender(&self) -> u8 {
+        match self.direction {
+            FlowDirection::Downward => self.upper,
+            FlowDirection::Upward => self.lower,
+        }
+    }
+
+    /// Boundary name, such as `"L4-L3"`
+    pub fn boundary(&self) -> String {
+        format!("L{}-L{}", self.upper, self.lower)
+    }
+
+    pub fn is_healthy(&self) -> bool {
+        !matches!(self.health, Some(CompressionHealth::PassThrough | CompressionHealth::Overcompressed))
+    }
+}
+
+/// Layer traffic within the window
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TrafficSnapshot {
+    pub window_secs: u64,
+    pub unit: TrafficUnit,
+    /// Content entering each layer, by layer number
+    pub entering: BTreeMap<u8, u64>,
+    /// Boundaries that carried traffic, top down, downward before upward
+    pub boundaries: Vec<BoundaryFlow>,
+    /// Backward signals that crossed each boundary, by boundary name
+    #[serde(default)]
+    pub backward_signals: BTreeMap<String, u64>,
+}
+
+impl TrafficSnapshot {
+    pub fn flow(&self, upper: u8, lower: u8, direction: FlowDirection) -> Option<&BoundaryFlow> {
+        self.boundaries.iter()
+            .find(|b| b.upper == upper && b.lower == lower && b.direction == direction)
+    }
+
+    /// Mean downward ratio over the boundaries that carried tasks, or `None`
+    /// before any have
+    pub fn compression_ratio(&self) -> Option<f64> {
+        let ratios: Vec<f64> = self.boundaries.iter()
+            .filter(|b| b.direction == FlowDirection::Downward)
+            .map(|b| b.ratio)
+            .collect();
+        if ratios.is_empty() {
+            None
+        } else {
+            Some(ratios.iter().sum::<f64>() / ratios.len() as f64)
+        }
+    }
+
+    /// Boundaries whose ratio is outside the healthy band
+    pub fn unhealthy(&self) -> impl Iterator<Item = &BoundaryFlow> {
+        self.boundaries.iter().filter(|b| !b.is_healthy())
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Sample {
+    at: Instant,
+    from_layer: u8,
+    to_layer: u8,
+    bytes: u64,
+    tokens: u64,
+    backward: bool,
+}
+
+/// Boundary name of two layer numbers, in either order, or `None` unless
+/// both are distinct layers of the hierarchy
+fn boundary_between(a: u8, b: u8) -> Option<String> {
+    (a != 0 && b != 0 && a != b).then(|| format!("L{}-L{}", a.max(b), a.min(b)))
+}
+
+/// Sliding window of signal traffic between layers
+pub struct LayerTraffic {
+    config: CompressionConfig,
+    samples: Mutex<VecDeque<Sample>>,
+    /// Backward signals per boundary since startup
+    backward_totals: Mutex<BTreeMap<String, u64>>,
+}
+
+impl LayerTraffic {
+    pub fn new(config: CompressionConfig) -> Self {
+        Self {
+            config,
+            samples: Mutex::new(VecDeque::new()),
+            backward_totals: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    pub fn config(&self) -> &CompressionConfig {
+        &self.config
+    }
+
+    /// Count content moving from `from_layer` to `to_layer`, by layer
+    /// number; 0 stands for anything outside the hierarchy
+    pub fn record(&self, from_layer: u8, to_layer: u8, bytes: u64, tokens: u64) {
+        self.push(from_layer, to_layer, bytes, tokens, false);
+    }
+
+    /// Count a backward signal, as content like any other and as gradient
+    /// activity across the boundary it crosses
+    pub fn record_backward(&self, from_layer: u8, to_layer: u8, bytes: u64, tokens: u64) {
+        if let Some(boundary) = boundary_between(from_layer, to_layer) {
+            *self.backward_totals.lock().entry(boundary).or_insert(0) += 1;
+        }
+        self.push(from_layer, to_layer, bytes, tokens, true);
+    }
+
+    pub fn record_signal(&self, signal: &NeuronSignal) {
+        let (bytes, tokens) = signal_size(signal);
+        let (from, to) = (layer_number(&signal.layer_from), layer_number(&signal.layer_to));
+        match signal.propagation_type {
+            PropagationType::Forward => self.record(from, to, bytes, tokens),
+            PropagationType::Backward => self.record_backward(from, to, bytes, tokens),
+        }
+    }
+
+    /// Backward signals that crossed each boundary since startup
+    pub fn backward_totals(&self) -> BTreeMap<String, u64> {
+        self.backward_totals.lock().clone()
+    }
+
+    fn push(&self, from_layer: u8, to_layer: u8, bytes: u64, tokens: u64, backward: bool) {
+        let now = Instant::now();
+        let mut samples = self.samples.lock();
+        Self::expire(&mut samples, now, self.window());
+        samples.push_back(Sample { at: now, from_layer, to_layer, bytes, tokens, backward });
+    }
+
+    /// Per-boundary ratios over the current window
+    pub fn snapshot(&self) -> TrafficSnapshot {
+        let mut samples = self.samples.lock();
+        Self::expire(&mut samples, Instant::now(), self.window());
+
+        let mut entering = BTreeMap::new();
+        let mut forwarded = BTreeMap::new();
+        let mut backward_signals = BTreeMap::new();
+        for sample in samples.iter() {
+            if sample.backward {
+                if let Some(boundary) = boundary_between(sample.from_layer, sample.to_layer) {
+                    *backward_signals.entry(boundary).or_insert(0) += 1;
+                }
+            }
+            let size = match self.config.unit {
+                TrafficUnit::Bytes => sample.bytes,
+                TrafficUnit::Tokens => sample.tokens,
+            };
+            if sample.to_layer != 0 {
+                *entering.entry(sample.to_layer).or_insert(0) += size;
+            }
+            if sample.from_layer != 0 && sample.to_layer != 0 && sample.from_layer != sample.to_layer {
+                let key = if sample.from_layer > sample.to_layer {
+                    (sample.from_layer, sample.to_layer, FlowDirection::Downward)
+                } else {
+                    (sample.to_layer, sample.from_layer, FlowDirection::Upward)
+                };
+                *forwarded.entry(key).or_insert(0) += size;
+            }
+        }
+
+        // Top down: highest upper layer first
+        let mut boundaries: Vec<BoundaryFlow> = forwarded.into_iter()
+            .filter_map(|((upper, lower, direction), forwarded)| {
+                let mut flow = BoundaryFlow {
+                    upper,
+                    lower,
+                    direction,
+                    entering: 0,
+                    forwarded,
+                    ratio: 0.0,
+                    health: None,
+                };
+                flow.entering = entering.get(&flow.sender()).copied().unwrap_or(0);
+                // Nothing in the window entered the sender, so nothing to compare
+                if flow.entering == 0 || flow.forwarded == 0 {
+                    return None;
+                }
+                flow.ratio = flow.entering as f64 / flow.forwarded as f64;
+                if direction == FlowDirection::Downward {
+                    flow.health = Some(self.health(flow.ratio));
+                }
+                Some(flow)
+            })
+            .collect();
+        boundaries.sort_by(|a, b| (b.upper, b.lower, a.direction).cmp(&(a.upper, a.lower, b.direction)));
+
+        TrafficSnapshot {
+            window_secs: self.config.window_secs,
+            unit: self.config.unit,
+            entering,
+            boundaries,
+            backward_signals,
+        }
+    }
+
+    fn health(&self, ratio: f64) -> CompressionHealth {
+        if ratio < self.config.healthy_min {
+            CompressionHealth::PassThrough
+        } else if ratio > self.config.healthy_max {
+            CompressionHealth::Overcompressed
+        } else {
+            CompressionHealth::Healthy
+        }
+    }
+
+    fn window(&self) -> Duration {
+        Duration::from_secs(self.config.window_secs)
+    }
+
+    fn expire(samples: &mut VecDeque<Sample>, now: Instant, window: Duration) {
+        while samples.front().is_some_and(|s| now.duration_since(s.at) >= window) {
+            samples.pop_front();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn traffic(unit: TrafficUnit) -> LayerTraffic {
+        LayerTraffic::new(CompressionConfig { unit, ..Default::default() })
+    }
+
+    #[test]
+    fn test_downward_ratio_per_boundary() {
+        let traffic = traffic(TrafficUnit::Bytes);
+        // A client request into L4, which L4 splits for L3, which hands one part to L2
+        traffic.record(0, 4, 3000, 0);
+        traffic.record(4, 3, 600, 0);
+        traffic.record(4, 3, 400, 0);
+        traffic.record(3, 2, 250, 0);
+
+        let snapshot = traffic.snapshot();
+        assert_eq!(snapshot.entering, BTreeMap::from([(4, 3000), (3, 1000), (2, 250)]));
+
+        let l4 = snapshot.flow(4, 3, FlowDirection::Downward).unwrap();
+        assert_eq!((l4.entering, l4.forwarded), (3000, 1000));
+        assert_eq!(l4.ratio, 3.0);
+        assert_eq!(l4.health, Some(CompressionHealth::Healthy));
+
+        let l3 = snapshot.flow(3, 2, FlowDirection::Downward).unwrap();
+        assert_eq!(l3.ratio, 4.0);
+        assert_eq!(snapshot.compression_ratio(), Some(3.5));
+        assert_eq!(snapshot.boundaries.iter().map(BoundaryFlow::boundary).collect::<Vec<_>>(), ["L4-L3", "L3-L2"]);
+    }
+
+    #[test]
+    fn test_upward_gradients_are_reported_unchecked() {
+        let traffic = traffic(TrafficUnit::Bytes);
+        traffic.record(4, 3, 800, 0);
+        traffic.record(3, 4, 40, 0);
+
+        let snapshot = traffic.snapshot();
+        let up = snapshot.flow(4, 3, FlowDirection::Upward).unwrap();
+        assert_eq!(up.sender(), 3);
+        assert_eq!(up.ratio, 20.0);
+        assert_eq!(up.health, None);
+        // L4 took in only the gradient, so it passes far more down than it received
+        let down = snapshot.flow(4, 3, FlowDirection::Downward).unwrap();
+        assert_eq!(down.ratio, 0.05);
+        assert_eq!(snapshot.compression_ratio(), Some(0.05));
+    }
+
+    #[test]
+    fn test_backward_signals_counted_per_boundary() {
+        let traffic = traffic(TrafficUnit::Bytes);
+        traffic.record(4, 3, 800, 0);
+        traffic.record_backward(3, 4, 40, 0);
+        traffic.record_backward(2, 4, 30, 0);
+        traffic.record_backward(3, 3, 20, 0);
+
+        let mut signal = NeuronSignal::backward("coder", "planner", "L2", "L3", crate::Gradient::new("timeout".to_string(), 1.0));
+        traffic.record_signal(&signal);
+        signal.layer_from = "API".to_string();
+        traffic.record_signal(&signal);
+
+        let snapshot = traffic.snapshot();
+        let expected = BTreeMap::from([("L3-L2".to_string(), 1), ("L4-L2".to_string(), 1), ("L4-L3".to_string(), 1)]);
+        assert_eq!(snapshot.backward_signals, expected);
+        assert_eq!(traffic.backward_totals(), expected);
+        // Gradients still count as content crossing upward
+        assert_eq!(snapshot.flow(4, 3, FlowDirection::Upward).unwrap().forwarded, 40);
+    }
+
+    #[test]
+    fn test_tokens_unit() {
+        let traffic = traffic(TrafficUnit::Tokens);
+        traffic.record(0, 3, 100, 12);
+        traffic.record(3, 2, 90, 4);
+
+        let flow = traffic.snapshot().boundaries[0].clone();
+        assert_eq!((flow.entering, flow.forwarded, flow.ratio), (12, 4, 3.0));
+    }
+
+    #[test]
+    fn test_unhealthy_boundaries_flagged() {
+        let traffic = traffic(TrafficUnit::Bytes);
+        traffic.record(0, 5, 1000, 0);
+        traffic.record(5, 4, 1000, 0);
+        traffic.record(0, 3, 2500, 0);
+        traffic.record(3, 2, 100, 0);
+
+        let snapshot = traffic.snapshot();
+        assert_eq!(snapshot.flow(5, 4, FlowDirection::Downward).unwrap().health, Some(CompressionHealth::PassThrough));
+        assert_eq!(snapshot.flow(3, 2, FlowDirection::Downward).unwrap().health, Some(CompressionHealth::Overcompressed));
+        assert_eq!(snapshot.unhealthy().count(), 2);
+    }
+
+    #[test]
+    fn test_signal_size_counts_activation_and_gradient() {
+        let mut signal = NeuronSignal::forward("a", "b", "L4", "L3", "split the task".to_string());
+        assert_eq!(signal_size(&signal), (14, 3));
+
+        signal.payload.gradient = Some(crate::Gradient::new("timeout".to_string(), 1.0));
+        assert_eq!(signal_size(&signal), (21, 4));
+    }
+
+    #[test]
+    fn test_samples_expire_with_window() {
+        let traffic = LayerTraffic::new(CompressionConfig { window_secs: 0, ..Default::default() });
+        traffic.record(0, 4, 100, 0);
+        traffic.record(4, 3, 50, 0);
+
+        let snapshot = traffic.snapshot();
+        assert!(snapshot.entering.is_empty());
+        assert!(snapshot.boundaries.is_empty());
+        assert_eq!(snapshot.compression_ratio(), None);
+    }
+}
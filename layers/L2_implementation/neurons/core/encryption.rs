@@ -0,0 +1,413 @@
+//! Per-organization encryption of stored payloads
+//!
+//! Every organization with encryption enabled gets its own data keys:
+//! random AES-256-GCM keys kept in the key store's `data_keys` table,
+//! wrapped by the master key the same way config secrets are (see
+//! [`crate::secrets`]). A value sealed for an organization is stored as
+//! `tenc:v1:<key id>:<base64>`, the random nonce followed by the
+//! ciphertext, so the key it needs travels with it and reads decrypt it
+//! whichever of the organization's keys wrote it.
+//!
+//! [`TenantKeyring::rotate`] gives an organization a new current key. Its
+//! retired keys stay to decrypt what they sealed; stores reseal such values
+//! under the current key when they read them, and a sweeper reseals the
+//! ones nobody reads. Values without the prefix are plaintext and read as
+//! they are, so turning encryption off for an organization only changes
+//! what new writes look like.
+
+use std::collections::{BTreeMap, HashSet};
+use std::sync::Arc;
+
+use aes_gcm::aead::{Aead, AeadCore, OsRng};
+use aes_gcm::{Aes256Gcm, Key, KeyInit, Nonce};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use parking_lot::RwLock;
+use serde::Serialize;
+use tracing::info;
+use uuid::Uuid;
+
+use crate::secrets::{MasterKeyProvider, SecretsCipher};
+use crate::sqlite::SqlitePools;
+use crate::{Error, Result};
+
+/// Prefix of values sealed for an organization
+pub const SEALED_PREFIX: &str = "tenc:";
+
+/// Current sealing format version
+pub const SEALED_VERSION: &str = "v1";
+
+const NONCE_LEN: usize = 12;
+
+/// Whether `value` was sealed for an organization rather than stored as is
+pub fn is_sealed(value: &str) -> bool {
+    value.starts_with(SEALED_PREFIX)
+}
+
+/// Prefix shared by every value sealed under `key_id`, for finding them
+/// with `LIKE`
+pub fn sealed_prefix(key_id: &str) -> String {
+    format!("{}{}:{}:", SEALED_PREFIX, SEALED_VERSION, key_id)
+}
+
+/// Key id and payload of a sealed value
+fn split_sealed(value: &str) -> Result<(&str, &str)> {
+    let rest = value
+        .strip_prefix(SEALED_PREFIX)
+        .ok_or_else(|| Error::InvalidInput("Value is not sealed".to_string()))?;
+    let (version, rest) = rest
+        .split_once(':')
+        .ok_or_else(|| Error::InvalidInput("Malformed sealed value".to_string()))?;
+    if version != SEALED_VERSION {
+        return Err(Error::InvalidInput(format!("Unsupported sealed value version '{}'", version)));
+    }
+    rest.split_once(':')
+        .ok_or_else(|| Error::InvalidInput("Malformed sealed value".to_string()))
+}
+
+/// One organization's data key, unwrapped
+struct DataKey {
+    org_id: String,
+    cipher: Aes256Gcm,
+    created_at: DateTime<Utc>,
+}
+
+/// An organization's keys as reported to admins
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct OrgKeyStatus {
+    pub org_id: String,
+    /// Whether new writes for the organization are sealed
+    pub encrypted: bool,
+    /// Key new values are sealed with
+    pub current_key: Option<String>,
+    /// Keys kept to decrypt values sealed before the last rotation
+    pub retired_keys: Vec<String>,
+    pub rotated_at: Option<DateTime<Utc>>,
+}
+
+/// Data keys of every organization, unwrapped in memory
+pub struct TenantKeyring {
+    pools: SqlitePools,
+    master: SecretsCipher,
+    keys: DashMap<String, Arc<DataKey>>,
+    /// Current key of each organization that has one
+    current: DashMap<String, String>,
+    encrypted_orgs: RwLock<HashSet<String>>,
+    /// Held while creating a key, so an organization never gets two current keys
+    rotation: tokio::sync::Mutex<()>,
+}
+
+impl TenantKeyring {
+    /// Open the key store on `pools` and unwrap its keys with the master
+    /// key. New writes are sealed for `encrypted_orgs`.
+    pub async fn open(
+        pools: SqlitePools,
+        master: &dyn MasterKeyProvider,
+        encrypted_orgs: impl IntoIterator<Item = String>,
+    ) -> Result<Self> {
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS data_keys (
+                key_id TEXT PRIMARY KEY,
+                org_id TEXT NOT NULL,
+                wrapped_key TEXT NOT NULL,
+                created_at INTEGER NOT NULL,
+                retired_at INTEGER
+            )",
+        )
+        .execute(pools.writer())
+        .await
+        .map_err(|e| Error::Storage(format!("Failed to create data_keys table: {}", e)))?;
+
+        let keyring = Self {
+            master: SecretsCipher::from_provider(master)?,
+            pools,
+            keys: DashMap::new(),
+            current: DashMap::new(),
+            encrypted_orgs: RwLock::new(encrypted_orgs.into_iter().collect()),
+            rotation: tokio::sync::Mutex::new(()),
+        };
+
+        let rows: Vec<(String, String, String, i64, Option<i64>)> =
+            sqlx::query_as("SELECT key_id, org_id, wrapped_key, created_at, retired_at FROM data_keys")
+                .fetch_all(keyring.pools.reader())
+                .await
+                .map_err(|e| Error::Storage(format!("Failed to load data keys: {}", e)))?;
+        for (key_id, org_id, wrapped_key, created_at, retired_at) in rows {
+            let key = keyring.unwrap_key(&key_id, org_id.clone(), &wrapped_key, created_at)?;
+            if retired_at.is_none() {
+                keyring.current.insert(org_id, key_id.clone());
+            }
+            keyring.keys.insert(key_id, Arc::new(key));
+        }
+        info!("Loaded {} data keys of {} organizations", keyring.keys.len(), keyring.current.len());
+        Ok(keyring)
+    }
+
+    fn unwrap_key(&self, key_id: &str, org_id: String, wrapped_key: &str, created_at: i64) -> Result<DataKey> {
+        let encoded = self
+            .master
+            .decrypt(wrapped_key)
+            .map_err(|e| Error::Config(format!("Failed to unwrap data key {}: {}", key_id, e)))?;
+        let bytes = BASE64
+            .decode(encoded)
+            .map_err(|_| Error::Config(format!("Data key {} is not valid base64", key_id)))?;
+        if bytes.len() != 32 {
+            return Err(Error::Config(format!("Data key {} must be 32 bytes, got {}", key_id, bytes.len())));
+        }
+        Ok(DataKey {
+            org_id,
+            cipher: Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&bytes)),
+            created_at: DateTime::from_timestamp(created_at, 0).unwrap_or_else(Utc::now),
+        })
+    }
+
+    /// Whether new writes for `org_id` are sealed
+    pub fn encrypts(&self, org_id: &str) -> bool {
+        self.encrypted_orgs.read().contains(org_id)
+    }
+
+    /// Seal new writes for `org_id`, or stop sealing them. Values sealed
+    /// before stay sealed and readable.
+    pub fn set_encrypted(&self, org_id: &str, encrypted: bool) {
+        let mut orgs = self.encrypted_orgs.write();
+        if encrypted {
+            orgs.insert(org_id.to_string());
+        } else {
+            orgs.remove(org_id);
+        }
+    }
+
+    /// Seal `plaintext` for `org_id` if the organization has encryption
+    /// enabled, creating its first key if needed; otherwise return it as is
+    pub async fn seal(&self, org_id: Option<&str>, plaintext: &str) -> Result<String> {
+        match org_id {
+            Some(org_id) if self.encrypts(org_id) => {
+                let key_id = match self.current_key(org_id) {
+                    Some(key_id) => key_id,
+                    None => self.create_key(org_id, false).await?,
+                };
+                self.seal_with(&key_id, plaintext)
+            }
+            _ => Ok(plaintext.to_string()),
+        }
+    }
+
+    fn seal_with(&self, key_id: &str, plaintext: &str) -> Result<String> {
+        let key = self
+            .keys
+            .get(key_id)
+            .ok_or_else(|| Error::NotFound(format!("Data key {} not found", key_id)))?;
+        let nonce = Aes256Gcm::generate_nonce(OsRng);
+        let ciphertext = key
+            .cipher
+            .encrypt(&nonce, plaintext.as_bytes())
+            .map_err(|_| Error::Processing(format!("Failed to seal value with key {}", key_id)))?;
+
+        let mut payload = nonce.to_vec();
+        payload.extend_from_slice(&ciphertext);
+        Ok(format!("{}{}", sealed_prefix(key_id), BASE64.encode(payload)))
+    }
+
+    /// Plaintext of a stored value; values that were not sealed are returned
+    /// as they are. Fails on an unknown key or a value altered at rest.
+    pub fn unseal(&self, value: &str) -> Result<String> {
+        if !is_sealed(value) {
+            return Ok(value.to_string());
+        }
+        let (key_id, encoded) = split_sealed(value)?;
+        let key = self
+            .keys
+            .get(key_id)
+            .ok_or_else(|| Error::NotFound(format!("Data key {} not found", key_id)))?;
+        let payload = BASE64
+            .decode(encoded)
+            .map_err(|_| Error::InvalidInput("Malformed sealed value".to_string()))?;
+        if payload.len() <= NONCE_LEN {
+            return Err(Error::InvalidInput("Malformed sealed value".to_string()));
+        }
+        let (nonce, ciphertext) = payload.split_at(NONCE_LEN);
+        let plaintext = key.cipher.decrypt(Nonce::from_slice(nonce), ciphertext).map_err(|_| {
+            Error::PermissionDenied(format!(
+                "Failed to unseal value with key {} of organization {}: wrong key or altered value",
+                key_id, key.org_id
+            ))
+        })?;
+        String::from_utf8(plaintext).map_err(|_| Error::InvalidInput("Unsealed value is not valid UTF-8".to_string()))
+    }
+
+    /// The value sealed under its organization's current key, if it is
+    /// sealed under a retired one
+    pub fn reseal(&self, value: &str) -> Result<Option<String>> {
+        if !is_sealed(value) {
+            return Ok(None);
+        }
+        let (key_id, _) = split_sealed(value)?;
+        let org_id = match self.keys.get(key_id) {
+            Some(key) => key.org_id.clone(),
+            None => return Err(Error::NotFound(format!("Data key {} not found", key_id))),
+        };
+        match self.current_key(&org_id) {
+            Some(current) if current != key_id => Ok(Some(self.seal_with(&current, &self.unseal(value)?)?)),
+            _ => Ok(None),
+        }
+    }
+
+    /// Key new values for `org_id` are sealed with
+    pub fn current_key(&self, org_id: &str) -> Option<String> {
+        self.current.get(org_id).map(|key_id| key_id.clone())
+    }
+
+    /// Keys that are no longer current, whose values need resealing
+    pub fn retired_keys(&self) -> Vec<String> {
+        let mut retired: Vec<String> = self
+            .keys
+            .iter()
+            .filter(|key| self.current_key(&key.org_id).as_deref() != Some(key.key().as_str()))
+            .map(|key| key.key().clone())
+            .collect();
+        retired.sort();
+        retired
+    }
+
+    /// Give `org_id` a new current key and retire its previous one,
+    /// returning the new key's id
+    pub async fn rotate(&self, org_id: &str) -> Result<String> {
+        self.create_key(org_id, true).await
+    }
+
+    async fn create_key(&self, org_id: &str, rotate: bool) -> Result<String> {
+        let _rotation = self.rotation.lock().await;
+        // Another write may have created the first key while this one waited
+        if let (false, Some(key_id)) = (rotate, self.current_key(org_id)) {
+            return Ok(key_id);
+        }
+
+        let raw = Aes256Gcm::generate_key(OsRng);
+        let wrapped_key = self.master.encrypt(&BASE64.encode(raw))?;
+        let key_id = format!("dek_{}", Uuid::new_v4().simple());
+        let now = Utc::now();
+        let (new_key, org, wrapped) = (&key_id, org_id, &wrapped_key);
+        self.pools
+            .write(|pool| async move {
+                let mut tx = pool.begin().await?;
+                sqlx::query("UPDATE data_keys SET retired_at = ? WHERE org_id = ? AND retired_at IS NULL")
+                    .bind(now.timestamp())
+                    .bind(org)
+                    .execute(&mut *tx)
+                    .await?;
+                sqlx::query("INSERT INTO data_keys (key_id, org_id, wrapped_key, created_at) VALUES (?, ?, ?, ?)")
+                    .bind(new_key)
+                    .bind(org)
+                    .bind(wrapped)
+                    .bind(now.timestamp())
+                    .execute(&mut *tx)
+                    .await?;
+                tx.commit().await
+            })
+            .await
+            .map_err(|e| Error::Storage(format!("Failed to store data key for {}: {}", org_id, e)))?;
+
+        let key = DataKey {
+            org_id: org_id.to_string(),
+            cipher: Aes256Gcm::new(&raw),
+            created_at: now,
+        };
+        self.keys.insert(key_id.clone(), Arc::new(key));
+        self.current.insert(org_id.to_string(), key_id.clone());
+        info!("Created data key {} for organization {}", key_id, org_id);
+        Ok(key_id)
+    }
+
+    /// Keys of every organization that has keys or has encryption enabled
+    pub fn status(&self) -> Vec<OrgKeyStatus> {
+        let mut orgs: BTreeMap<String, OrgKeyStatus> = self
+            .encrypted_orgs
+            .read()
+            .iter()
+            .map(|org_id| (org_id.clone(), self.empty_status(org_id)))
+            .collect();
+        for key in self.keys.iter() {
+            let status = orgs
+                .entry(key.org_id.clone())
+                .or_insert_with(|| self.empty_status(&key.org_id));
+            if status.current_key.as_deref() == Some(key.key().as_str()) {
+                status.rotated_at = Some(key.created_at);
+            } else {
+                status.retired_keys.push(key.key().clone());
+            }
+        }
+        orgs.into_values()
+            .map(|mut status| {
+                status.retired_keys.sort();
+                status
+            })
+            .collect()
+    }
+
+    /// Keys of one organization
+    pub fn org_status(&self, org_id: &str) -> OrgKeyStatus {
+        self.status()
+            .into_iter()
+            .find(|status| status.org_id == org_id)
+            .unwrap_or_else(|| self.empty_status(org_id))
+    }
+
+    fn empty_status(&self, org_id: &str) -> OrgKeyStatus {
+        OrgKeyStatus {
+            org_id: org_id.to_string(),
+            encrypted: self.encrypts(org_id),
+            current_key: self.current_key(org_id),
+            retired_keys: Vec::new(),
+            rotated_at: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::secrets::{MasterKey, StaticKeyProvider};
+    use crate::sqlite::SqliteTuning;
+
+    async fn keyring(master: &MasterKey, pools: SqlitePools, orgs: &[&str]) -> TenantKeyring {
+        let orgs = orgs.iter().map(|org| org.to_string());
+        TenantKeyring::open(pools, &StaticKeyProvider(master.clone()), orgs).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_keys_survive_reopening_only_with_the_master_key() {
+        let pools = SqlitePools::connect("sqlite::memory:", SqliteTuning::default()).await.unwrap();
+        let master = MasterKey::generate();
+        let first = keyring(&master, pools.clone(), &["acme"]).await;
+        let sealed = first.seal(Some("acme"), "launch codes").await.unwrap();
+        assert!(sealed.starts_with("tenc:v1:dek_"), "{}", sealed);
+        assert!(!sealed.contains("launch"));
+        assert_eq!(first.seal(None, "public").await.unwrap(), "public");
+        assert_eq!(first.seal(Some("other"), "public").await.unwrap(), "public");
+
+        let reopened = keyring(&master, pools.clone(), &[]).await;
+        assert_eq!(reopened.unseal(&sealed).unwrap(), "launch codes");
+        assert_eq!(reopened.current_key("acme"), first.current_key("acme"));
+
+        let err = TenantKeyring::open(pools, &StaticKeyProvider(MasterKey::generate()), Vec::new())
+            .await
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("Failed to unwrap data key"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn test_malformed_values_fail() {
+        let pools = SqlitePools::connect("sqlite::memory:", SqliteTuning::default()).await.unwrap();
+        let keyring = keyring(&MasterKey::generate(), pools, &["acme"]).await;
+        let sealed = keyring.seal(Some("acme"), "secret").await.unwrap();
+
+        assert!(keyring.unseal("tenc:v1:dek_missing:AAAA").unwrap_err().to_string().contains("not found"));
+        assert!(keyring.unseal(&sealed.replacen("v1", "v9", 1)).unwrap_err().to_string().contains("'v9'"));
+        let key_id = keyring.current_key("acme").unwrap();
+        let truncated = format!("{}AAAA", sealed_prefix(&key_id));
+        assert!(keyring.unseal(&truncated).unwrap_err().to_string().contains("Malformed"));
+    }
+}
@@ -30,9 +30,18 @@ pub enum Error {
     #[error("Timeout after {0} seconds")]
     Timeout(u64),
     
+    #[error("Neuron {neuron} call exceeded its adaptive timeout of {timeout_ms} ms")]
+    AdaptiveTimeout { neuron: String, timeout_ms: u64 },
+    
+    #[error("Neuron {neuron} call ran past the signal's deadline {deadline}")]
+    DeadlineExceeded { neuron: String, deadline: String },
+    
     #[error("Cost limit exceeded: {reason}")]
     CostLimit { reason: String },
     
+    #[error("Model {model} is retired and models.remap has no replacement for it")]
+    ModelRetired { model: String },
+    
     #[error("Circuit breaker open for {service}")]
     CircuitBreakerOpen { service: String },
     
@@ -45,6 +54,9 @@ pub enum Error {
     #[error("Tool execution error: {0}")]
     ToolExecution(String),
     
+    #[error("Chain {chain_id} exceeded its tool {limit} limit: {message}")]
+    ToolLimitExceeded { chain_id: String, limit: String, message: String },
+    
     #[error("Network error: {0}")]
     Network(String),
     
@@ -69,6 +81,15 @@ pub enum Error {
     #[error("Storage error: {0}")]
     Storage(String),
     
+    #[error("Permission denied: {0}")]
+    PermissionDenied(String),
+    
+    #[error("Quota exceeded: {0}")]
+    QuotaExceeded(String),
+    
+    #[error("Read-only: {0}")]
+    ReadOnly(String),
+    
     #[error("Deserialization error: {0}")]
     Deserialization(String),
     
@@ -95,11 +116,55 @@ pub enum Error {
 }
 
 impl Error {
+    /// Short name of the error's kind, e.g. `timeout` or `claude_api`, as
+    /// carried by the gradients of backward signals
+    pub fn class(&self) -> &'static str {
+        match self {
+            Error::Config(_) => "config",
+            Error::Neuron { .. } => "neuron",
+            Error::Routing(_) => "routing",
+            Error::Process(_) => "process",
+            Error::Communication(_) => "communication",
+            Error::ClaudeApi(_) => "claude_api",
+            Error::RateLimit => "rate_limit",
+            Error::Timeout(_) => "timeout",
+            Error::AdaptiveTimeout { .. } => "adaptive_timeout",
+            Error::DeadlineExceeded { .. } => "deadline_exceeded",
+            Error::CostLimit { .. } => "cost_limit",
+            Error::ModelRetired { .. } => "model_retired",
+            Error::CircuitBreakerOpen { .. } => "circuit_breaker_open",
+            Error::InvalidState(_) => "invalid_state",
+            Error::InvalidInput(_) => "invalid_input",
+            Error::ToolExecution(_) => "tool_execution",
+            Error::ToolLimitExceeded { .. } => "tool_limit_exceeded",
+            Error::Network(_) => "network",
+            Error::Serialization(_) => "serialization",
+            Error::Protocol(_) => "protocol",
+            Error::NotFound(_) => "not_found",
+            Error::ResourceExhausted(_) => "resource_exhausted",
+            Error::ResourceNotFound(_) => "resource_not_found",
+            Error::Transport(_) => "transport",
+            Error::Storage(_) => "storage",
+            Error::PermissionDenied(_) => "permission_denied",
+            Error::QuotaExceeded(_) => "quota_exceeded",
+            Error::ReadOnly(_) => "read_only",
+            Error::Deserialization(_) => "deserialization",
+            Error::Processing(_) => "processing",
+            Error::Runtime(_) => "runtime",
+            Error::Migration(_) => "migration",
+            Error::Configuration(_) => "configuration",
+            Error::Io(_) => "io",
+            Error::Json(_) => "json",
+            Error::Other(_) => "other",
+        }
+    }
+    
     /// Check if error is recoverable
     pub fn is_recoverable(&self) -> bool {
         matches!(self, 
             Error::RateLimit | 
             Error::Timeout(_) | 
+            Error::AdaptiveTimeout { .. } |
             Error::Communication(_) |
             Error::CircuitBreakerOpen { .. }
         )
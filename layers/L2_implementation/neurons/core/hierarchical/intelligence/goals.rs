@@ -0,0 +1,163 @@
+//! Goal decomposition into work the neuron hierarchy can execute
+//!
+//! A [`Goal`] is split into [`GoalTask`]s according to its
+//! [`DecompositionStrategy`]; each task becomes one root signal. Success
+//! criteria are scored by an evaluator whose answer is parsed with
+//! [`parse_evaluation`].
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::{Criterion, DecompositionStrategy, Goal, Measurement};
+
+/// Layer that receives tasks needing strategic breakdown
+pub const STRATEGIC_LAYER: &str = "L5";
+
+/// Layer that receives narrowly scoped tasks
+pub const TACTICAL_LAYER: &str = "L4";
+
+/// One unit of work, submitted as a root signal to `layer`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GoalTask {
+    pub layer: String,
+    pub content: String,
+}
+
+/// How a plan's tasks are released
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskRelease {
+    /// All tasks are submitted at once
+    Concurrent,
+    /// Each task is submitted once the previous one's chain finishes
+    Sequential,
+}
+
+/// Tasks a goal decomposes into
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GoalPlan {
+    pub tasks: Vec<GoalTask>,
+    pub release: TaskRelease,
+    /// Whether another attempt is made while criteria remain unmet after
+    /// every task finished
+    pub retry_until_achieved: bool,
+}
+
+impl GoalPlan {
+    /// Follow-up task for another attempt, naming the criteria still unmet
+    pub fn retry_task(&self, goal: &Goal, unmet: &[&Criterion]) -> GoalTask {
+        let shortfall: Vec<_> = unmet
+            .iter()
+            .map(|criterion| format!("{} (target {})", criterion.name, criterion.target_value))
+            .collect();
+        GoalTask {
+            layer: TACTICAL_LAYER.to_string(),
+            content: format!(
+                "{}\n\nA previous attempt fell short on: {}",
+                describe(goal),
+                shortfall.join(", ")
+            ),
+        }
+    }
+}
+
+/// Split a goal into tasks according to its decomposition strategy
+pub fn decompose(goal: &Goal) -> GoalPlan {
+    let per_criterion = || -> Vec<GoalTask> {
+        if goal.success_criteria.is_empty() {
+            return vec![GoalTask {
+                layer: TACTICAL_LAYER.to_string(),
+                content: describe(goal),
+            }];
+        }
+        goal.success_criteria
+            .iter()
+            .map(|criterion| GoalTask {
+                layer: TACTICAL_LAYER.to_string(),
+                content: format!(
+                    "{}\n\nFocus on: {} (target {})",
+                    goal.description, criterion.name, criterion.target_value
+                ),
+            })
+            .collect()
+    };
+
+    match goal.decomposition_strategy {
+        DecompositionStrategy::Hierarchical => GoalPlan {
+            tasks: vec![GoalTask {
+                layer: STRATEGIC_LAYER.to_string(),
+                content: describe(goal),
+            }],
+            release: TaskRelease::Concurrent,
+            retry_until_achieved: false,
+        },
+        DecompositionStrategy::Parallel => GoalPlan {
+            tasks: per_criterion(),
+            release: TaskRelease::Concurrent,
+            retry_until_achieved: false,
+        },
+        DecompositionStrategy::Sequential => GoalPlan {
+            tasks: per_criterion(),
+            release: TaskRelease::Sequential,
+            retry_until_achieved: false,
+        },
+        DecompositionStrategy::Adaptive => GoalPlan {
+            tasks: vec![GoalTask {
+                layer: TACTICAL_LAYER.to_string(),
+                content: describe(goal),
+            }],
+            release: TaskRelease::Sequential,
+            retry_until_achieved: true,
+        },
+    }
+}
+
+/// Goal description with its success criteria spelled out
+fn describe(goal: &Goal) -> String {
+    if goal.success_criteria.is_empty() {
+        return goal.description.clone();
+    }
+    let criteria: Vec<_> = goal.success_criteria
+        .iter()
+        .map(|criterion| format!("- {} (target {})", criterion.name, criterion.target_value))
+        .collect();
+    format!("{}\n\nSuccess criteria:\n{}", goal.description, criteria.join("\n"))
+}
+
+/// Prompt asking an evaluator to score `outputs` against the goal's criteria
+pub fn evaluation_prompt(goal: &Goal, outputs: &[String]) -> String {
+    let criteria: Vec<_> = goal.success_criteria.iter().map(|criterion| criterion.name.clone()).collect();
+    format!(
+        "Evaluate progress on this goal.\n\n{}\n\nResults so far:\n{}\n\nAnswer with one line per criterion in the form `name: value`, covering: {}",
+        describe(goal),
+        outputs.join("\n---\n"),
+        criteria.join(", ")
+    )
+}
+
+/// Criterion scores from an evaluator answer of `name: value` lines.
+/// Lines that do not parse are ignored.
+pub fn parse_evaluation(answer: &str) -> HashMap<String, f32> {
+    answer
+        .lines()
+        .filter_map(|line| {
+            let (name, value) = line.split_once(':')?;
+            let name = name.trim().trim_start_matches(['-', '*']).trim().trim_matches('`');
+            let value = value.trim().trim_matches('`').trim_end_matches('%').parse::<f32>().ok()?;
+            (!name.is_empty()).then(|| (name.to_string(), value))
+        })
+        .collect()
+}
+
+impl Criterion {
+    /// Whether a measured value satisfies the criterion. Relative criteria
+    /// compare the ratio to their baseline against the target.
+    pub fn is_met(&self, value: f32) -> bool {
+        match &self.measurement {
+            Measurement::Absolute | Measurement::Percentile { .. } => value >= self.target_value,
+            Measurement::Relative { baseline } if *baseline != 0.0 => value / baseline >= self.target_value,
+            Measurement::Relative { .. } => false,
+        }
+    }
+}
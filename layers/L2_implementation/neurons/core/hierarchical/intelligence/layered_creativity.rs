@@ -0,0 +1,709 @@
+//! Constraint-aware idea generation through layered prompting
+//!
+//! Candidate ideas are asked for at the abstract end of the hierarchy (L9/L8)
+//! and each one is walked down to strategic (L5) and operational (L3)
+//! sketches. Novelty is the embedding distance from a library of strategies
+//! the system already knows, feasibility comes from a rubric prompt, and
+//! candidates breaking a resource, time, quality or ethical constraint are
+//! set aside together with the reasons.
+
+use async_trait::async_trait;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::sync::Arc;
+use uuid::Uuid;
+use crate::Result;
+use crate::learning::Mitigation;
+use crate::memory::EmbeddingGenerator;
+use super::*;
+
+/// Dimension of the embeddings used for novelty scoring
+const EMBEDDING_DIMENSION: usize = 256;
+
+/// Rubric score assumed when the response omits a criterion
+const NEUTRAL_RUBRIC_SCORE: f32 = 5.0;
+
+/// Sends a prompt to a model reasoning at a given hierarchy level
+#[async_trait]
+pub trait LayerPrompter: Send + Sync {
+    async fn prompt(&self, level: u8, prompt: &str) -> Result<String>;
+}
+
+/// Name and focus of a hierarchy level, as used by the HA prompter
+pub fn level_focus(level: u8) -> (&'static str, &'static str) {
+    match level {
+        1 => ("Reflexive", "Immediate responses, concrete actions, reflexive behavior"),
+        2 => ("Implementation", "How to implement, code, build, and create"),
+        3 => ("Operational", "Day-to-day operations, running systems, maintenance"),
+        4 => ("Tactical", "Short-term planning, tactics, immediate goals"),
+        5 => ("Strategic", "Long-term strategy, architecture, system design"),
+        6 => ("Executive", "Executive decisions, resource allocation, leadership"),
+        7 => ("Business", "Business value, market positioning, sustainability"),
+        8 => ("Visionary", "Future vision, possibilities, long-term evolution"),
+        _ => ("Universal", "Universal principles, philosophy, existence itself"),
+    }
+}
+
+/// Tuning for the layered creativity pipeline
+#[derive(Debug, Clone)]
+pub struct LayeredCreativityConfig {
+    /// Levels asked for candidate ideas, most abstract first
+    pub ideation_levels: Vec<u8>,
+    /// Levels each idea is expanded through, most abstract first
+    pub expansion_levels: Vec<u8>,
+    /// Level answering the feasibility rubric
+    pub rubric_level: u8,
+    /// Ideas requested per ideation level
+    pub ideas_per_level: usize,
+    /// Upper bound on candidates carried into expansion
+    pub max_candidates: usize,
+    /// Weight of novelty against feasibility when ranking solutions
+    pub novelty_weight: f32,
+}
+
+impl Default for LayeredCreativityConfig {
+    fn default() -> Self {
+        Self {
+            ideation_levels: vec![9, 8],
+            expansion_levels: vec![5, 3],
+            rubric_level: 5,
+            ideas_per_level: 3,
+            max_candidates: 6,
+            novelty_weight: 0.5,
+        }
+    }
+}
+
+impl LayeredCreativityConfig {
+    /// Every level the pipeline prompts, in ascending order
+    pub fn levels(&self) -> Vec<u8> {
+        let mut levels: Vec<u8> = self.ideation_levels.iter()
+            .chain(&self.expansion_levels)
+            .chain(std::iter::once(&self.rubric_level))
+            .copied()
+            .collect();
+        levels.sort_unstable();
+        levels.dedup();
+        levels
+    }
+}
+
+/// Strategies the system already knows; ideas close to one of them are not novel
+#[derive(Debug, Clone, Default)]
+pub struct StrategyLibrary {
+    strategies: Vec<String>,
+}
+
+impl StrategyLibrary {
+    pub fn new(strategies: Vec<String>) -> Self {
+        Self { strategies }
+    }
+
+    /// The tactical strategy templates every L4 executor starts with
+    pub fn with_defaults() -> Self {
+        Self::new(vec![
+            "Incremental Development: start with minimal viable product, add features iteratively, gather feedback at each iteration, refactor and optimize regularly".to_string(),
+            "Divide and Conquer: break problem into smaller subproblems, solve subproblems independently, integrate solutions, optimize the overall solution".to_string(),
+            "Test-Driven Approach: write tests first, implement minimal code to pass tests, refactor while keeping tests green, add more tests for edge cases".to_string(),
+        ])
+    }
+
+    /// Add mitigations that worked for recurring errors
+    pub fn add_mitigations(&mut self, mitigations: &[Mitigation]) {
+        for mitigation in mitigations {
+            let mut strategy = mitigation.strategy.clone();
+            if !mitigation.adjustments_applied.is_empty() {
+                strategy = format!("{}: {}", strategy, mitigation.adjustments_applied.join(", "));
+            }
+            self.strategies.push(strategy);
+        }
+    }
+
+    pub fn strategies(&self) -> &[String] {
+        &self.strategies
+    }
+}
+
+/// Solutions that passed every constraint, and the candidates that did not
+#[derive(Debug, Clone, Serialize)]
+pub struct CreationReport {
+    pub challenge_id: Uuid,
+    pub solutions: Vec<Solution>,
+    pub rejected: Vec<RejectedIdea>,
+}
+
+/// Candidate set aside during creation
+#[derive(Debug, Clone, Serialize)]
+pub struct RejectedIdea {
+    pub idea: Idea,
+    pub reasons: Vec<String>,
+}
+
+/// Feasibility rubric answer
+#[derive(Debug, Clone, PartialEq)]
+pub struct RubricScores {
+    pub technical: f32,
+    pub resources: f32,
+    pub risk: f32,
+    pub violations: Vec<String>,
+}
+
+impl RubricScores {
+    /// Mean rubric score scaled to 0-1
+    pub fn feasibility(&self) -> f32 {
+        ((self.technical + self.resources + self.risk) / 30.0).clamp(0.0, 1.0)
+    }
+}
+
+/// Cost and effort an expansion reported for an idea
+#[derive(Debug, Clone, Default)]
+struct Estimate {
+    cost: Option<f32>,
+    hours: Option<f32>,
+}
+
+/// An idea walked down through the expansion levels
+struct Expansion {
+    steps: Vec<ImplementationStep>,
+    milestones: Vec<(u8, Vec<String>)>,
+    estimate: Estimate,
+}
+
+/// Creativity engine prompting the hierarchy from vision down to operations
+pub struct LayeredCreativityEngine {
+    prompter: Arc<dyn LayerPrompter>,
+    config: LayeredCreativityConfig,
+    library: StrategyLibrary,
+    embeddings: EmbeddingGenerator,
+    blender: CreativeSystem,
+}
+
+impl LayeredCreativityEngine {
+    pub fn new(prompter: Arc<dyn LayerPrompter>, config: LayeredCreativityConfig, library: StrategyLibrary) -> Self {
+        Self {
+            prompter,
+            config,
+            library,
+            embeddings: EmbeddingGenerator::new(EMBEDDING_DIMENSION),
+            blender: CreativeSystem::new(),
+        }
+    }
+
+    /// One minus the highest cosine similarity to any known strategy
+    pub async fn novelty(&self, text: &str) -> Result<f32> {
+        let embedding = self.embeddings.generate(text).await?;
+        let mut closest = 0.0f32;
+        for strategy in self.library.strategies() {
+            let known = self.embeddings.generate(strategy).await?;
+            closest = closest.max(EmbeddingGenerator::cosine_similarity(&embedding, &known));
+        }
+        Ok((1.0 - closest).clamp(0.0, 1.0))
+    }
+
+    /// Candidate ideas from every ideation level, deduplicated and capped
+    async fn ideate(&self, problem: &str, constraints: &[Constraint]) -> Result<Vec<Idea>> {
+        let mut seen = HashSet::new();
+        let mut ideas = Vec::new();
+
+        for &level in &self.config.ideation_levels {
+            let prompt = ideation_prompt(level, problem, constraints, self.config.ideas_per_level);
+            let response = self.prompter.prompt(level, &prompt).await?;
+
+            for description in tagged_lines(&response, "IDEA").into_iter().take(self.config.ideas_per_level) {
+                if ideas.len() >= self.config.max_candidates {
+                    break;
+                }
+                if !seen.insert(description.to_lowercase()) {
+                    continue;
+                }
+                let (name, _) = level_focus(level);
+                ideas.push(Idea {
+                    id: Uuid::new_v4(),
+                    novelty: self.novelty(&description).await?,
+                    description,
+                    inspiration_sources: vec![format!("L{} {}", level, name)],
+                });
+            }
+        }
+
+        Ok(ideas)
+    }
+
+    /// Walk an idea down the expansion levels, each refining the one above
+    async fn expand(&self, problem: &str, idea: &Idea) -> Result<Expansion> {
+        let mut expansion = Expansion {
+            steps: Vec::new(),
+            milestones: Vec::new(),
+            estimate: Estimate::default(),
+        };
+        let mut previous: Vec<String> = Vec::new();
+
+        for &level in &self.config.expansion_levels {
+            let prompt = expansion_prompt(level, problem, &idea.description, &previous);
+            let response = self.prompter.prompt(level, &prompt).await?;
+
+            let steps = tagged_lines(&response, "STEP");
+            expansion.steps.extend(steps.iter().map(|step| ImplementationStep {
+                description: step.clone(),
+                assigned_layers: vec![level],
+                dependencies: vec![],
+            }));
+            if let Some(cost) = tagged_number(&response, "COST") {
+                expansion.estimate.cost = Some(cost);
+            }
+            if let Some(hours) = tagged_number(&response, "HOURS") {
+                expansion.estimate.hours = Some(hours);
+            }
+            expansion.milestones.push((level, steps.clone()));
+            previous = steps;
+        }
+
+        Ok(expansion)
+    }
+
+    /// Score an expanded idea against the feasibility rubric
+    async fn rubric(&self, idea: &Idea, expansion: &Expansion, constraints: &[Constraint]) -> Result<RubricScores> {
+        let principles: Vec<&str> = constraints.iter()
+            .filter_map(|c| match &c.constraint_type {
+                ConstraintType::Ethical { principles } => Some(principles.iter().map(String::as_str)),
+                _ => None,
+            })
+            .flatten()
+            .collect();
+
+        let level = self.config.rubric_level;
+        let prompt = rubric_prompt(level, &idea.description, &expansion.steps, &principles);
+        let response = self.prompter.prompt(level, &prompt).await?;
+        Ok(parse_rubric(&response))
+    }
+
+    /// Expand, score and constraint-check one idea
+    async fn evaluate(&self, challenge: &Challenge, idea: &Idea) -> Result<std::result::Result<Solution, Vec<String>>> {
+        let expansion = self.expand(&challenge.problem_statement, idea).await?;
+        if expansion.steps.is_empty() {
+            return Ok(Err(vec!["expansion produced no implementation steps".to_string()]));
+        }
+
+        let rubric = self.rubric(idea, &expansion, &challenge.constraints).await?;
+        let feasibility = rubric.feasibility();
+        let reasons = constraint_violations(&challenge.constraints, &expansion.estimate, &rubric, chrono::Utc::now());
+        if !reasons.is_empty() {
+            return Ok(Err(reasons));
+        }
+
+        Ok(Ok(build_solution(idea, expansion, feasibility)))
+    }
+
+    fn rank(&self, solution: &Solution) -> f32 {
+        let weight = self.config.novelty_weight.clamp(0.0, 1.0);
+        weight * solution.novelty_score + (1.0 - weight) * solution.feasibility_score
+    }
+}
+
+#[async_trait]
+impl CreativityEngine for LayeredCreativityEngine {
+    async fn generate_ideas(&self, constraints: &[Constraint]) -> Result<Vec<Idea>> {
+        self.ideate("", constraints).await
+    }
+
+    async fn combine_concepts(&self, concepts: &[Concept]) -> Result<Vec<NovelConcept>> {
+        // Blending has no prompting stage
+        self.blender.combine_concepts(concepts).await
+    }
+
+    async fn evaluate_novelty(&self, solution: &Solution) -> Result<f32> {
+        self.novelty(&solution.description).await
+    }
+
+    async fn create_solutions(&self, challenge: &Challenge) -> Result<CreationReport> {
+        let ideas = self.ideate(&challenge.problem_statement, &challenge.constraints).await?;
+
+        let mut solutions = Vec::new();
+        let mut rejected = Vec::new();
+        for idea in ideas {
+            match self.evaluate(challenge, &idea).await {
+                Ok(Ok(solution)) => solutions.push(solution),
+                Ok(Err(reasons)) => rejected.push(RejectedIdea { idea, reasons }),
+                Err(e) => rejected.push(RejectedIdea {
+                    reasons: vec![format!("evaluation failed: {}", e)],
+                    idea,
+                }),
+            }
+        }
+
+        solutions.sort_by(|a, b| self.rank(b).total_cmp(&self.rank(a)));
+
+        Ok(CreationReport {
+            challenge_id: challenge.id,
+            solutions,
+            rejected,
+        })
+    }
+}
+
+fn level_header(level: u8) -> String {
+    let (name, focus) = level_focus(level);
+    format!("You are reasoning at L{} ({}): {}.", level, name, focus)
+}
+
+fn describe_constraint(constraint: &Constraint) -> String {
+    match &constraint.constraint_type {
+        ConstraintType::Resource { max_cost } => format!("cost at most {:.2}", max_cost),
+        ConstraintType::Time { deadline } => format!("done before {}", deadline.to_rfc3339()),
+        ConstraintType::Quality { min_score } => format!("feasibility at least {:.2}", min_score),
+        ConstraintType::Ethical { principles } => format!("respect {}", principles.join(", ")),
+    }
+}
+
+fn ideation_prompt(level: u8, problem: &str, constraints: &[Constraint], count: usize) -> String {
+    let mut prompt = level_header(level);
+    if problem.is_empty() {
+        prompt.push_str(&format!("\nPropose {} distinct ideas worth exploring.", count));
+    } else {
+        prompt.push_str(&format!("\nPropose {} distinct ideas for: {}", count, problem));
+    }
+    if !constraints.is_empty() {
+        let constraints: Vec<String> = constraints.iter().map(describe_constraint).collect();
+        prompt.push_str(&format!("\nConstraints: {}", constraints.join("; ")));
+    }
+    prompt.push_str("\nAnswer with one line per idea, formatted as IDEA: <idea>");
+    prompt
+}
+
+fn expansion_prompt(level: u8, problem: &str, idea: &str, previous: &[String]) -> String {
+    let mut prompt = level_header(level);
+    prompt.push_str(&format!("\nTurn this idea into concrete steps: {}", idea));
+    if !problem.is_empty() {
+        prompt.push_str(&format!("\nProblem: {}", problem));
+    }
+    if !previous.is_empty() {
+        prompt.push_str(&format!("\nRefine these steps from the level above: {}", previous.join("; ")));
+    }
+    prompt.push_str("\nAnswer with one line per step, formatted as STEP: <step>, then COST: <total cost> and HOURS: <total hours>");
+    prompt
+}
+
+fn rubric_prompt(level: u8, idea: &str, steps: &[ImplementationStep], principles: &[&str]) -> String {
+    let steps: Vec<&str> = steps.iter().map(|s| s.description.as_str()).collect();
+    let mut prompt = level_header(level);
+    prompt.push_str(&format!("\nRate the feasibility of: {}\nPlan: {}", idea, steps.join("; ")));
+    prompt.push_str("\nScore each from 0 to 10, higher is better: TECHNICAL: <score>, RESOURCES: <score>, RISK: <score>");
+    if !principles.is_empty() {
+        prompt.push_str(&format!(
+            "\nFor each of these principles the plan breaks, add a line VIOLATES: <principle>: {}",
+            principles.join(", ")
+        ));
+    }
+    prompt
+}
+
+/// Values of lines starting with `TAG:`, ignoring case and list markers
+fn tagged_lines(response: &str, tag: &str) -> Vec<String> {
+    response.lines()
+        .filter_map(|line| {
+            let line = line.trim().trim_start_matches(['-', '*', ' ']);
+            let (key, value) = line.split_once(':')?;
+            let key = key.trim().trim_start_matches(|c: char| c.is_ascii_digit() || c == '.' || c == ' ');
+            let value = value.trim();
+            (key.eq_ignore_ascii_case(tag) && !value.is_empty()).then(|| value.to_string())
+        })
+        .collect()
+}
+
+/// First number on a `TAG:` line
+fn tagged_number(response: &str, tag: &str) -> Option<f32> {
+    tagged_lines(response, tag).iter().find_map(|value| {
+        let number: String = value.chars()
+            .skip_while(|c| !c.is_ascii_digit())
+            .take_while(|c| c.is_ascii_digit() || *c == '.')
+            .collect();
+        number.parse().ok()
+    })
+}
+
+fn parse_rubric(response: &str) -> RubricScores {
+    let score = |tag| tagged_number(response, tag)
+        .map(|s: f32| s.clamp(0.0, 10.0))
+        .unwrap_or(NEUTRAL_RUBRIC_SCORE);
+    RubricScores {
+        technical: score("TECHNICAL"),
+        resources: score("RESOURCES"),
+        risk: score("RISK"),
+        violations: tagged_lines(response, "VIOLATES")
+            .into_iter()
+            .filter(|v| !v.eq_ignore_ascii_case("none"))
+            .collect(),
+    }
+}
+
+/// Reasons an idea breaks the challenge constraints; empty if it fits
+fn constraint_violations(
+    constraints: &[Constraint],
+    estimate: &Estimate,
+    rubric: &RubricScores,
+    now: chrono::DateTime<chrono::Utc>,
+) -> Vec<String> {
+    let mut reasons = Vec::new();
+
+    for constraint in constraints {
+        match &constraint.constraint_type {
+            ConstraintType::Resource { max_cost } => {
+                if let Some(cost) = estimate.cost.filter(|cost| cost > max_cost) {
+                    reasons.push(format!("resource: estimated cost {:.2} exceeds limit {:.2}", cost, max_cost));
+                }
+            }
+            ConstraintType::Time { deadline } => {
+                if let Some(hours) = estimate.hours {
+                    let finish = now + chrono::Duration::seconds((hours * 3600.0) as i64);
+                    if finish > *deadline {
+                        reasons.push(format!(
+                            "time: estimated {:.1}h finishes after deadline {}",
+                            hours,
+                            deadline.to_rfc3339()
+                        ));
+                    }
+                }
+            }
+            ConstraintType::Quality { min_score } => {
+                if rubric.feasibility() < *min_score {
+                    reasons.push(format!(
+                        "quality: feasibility {:.2} below minimum {:.2}",
+                        rubric.feasibility(),
+                        min_score
+                    ));
+                }
+            }
+            ConstraintType::Ethical { principles } => {
+                for principle in principles {
+                    let broken = rubric.violations.iter()
+                        .any(|v| v.to_lowercase().contains(&principle.to_lowercase()));
+                    if broken {
+                        reasons.push(format!("ethical: violates principle '{}'", principle));
+                    }
+                }
+            }
+        }
+    }
+
+    reasons
+}
+
+fn build_solution(idea: &Idea, expansion: Expansion, feasibility: f32) -> Solution {
+    let hours = expansion.estimate.hours.unwrap_or(0.0);
+    let duration = std::time::Duration::from_secs_f32(hours.max(0.0) * 3600.0);
+    let start = chrono::Utc::now();
+    let stages = expansion.milestones.len().max(1) as f32;
+
+    let milestones = expansion.milestones.into_iter()
+        .enumerate()
+        .map(|(i, (level, deliverables))| {
+            let (name, _) = level_focus(level);
+            let offset = hours * (i + 1) as f32 / stages;
+            Milestone {
+                name: format!("L{} {} sketch", level, name),
+                expected_completion: start + chrono::Duration::seconds((offset * 3600.0) as i64),
+                deliverables,
+            }
+        })
+        .collect();
+
+    Solution {
+        id: idea.id,
+        description: idea.description.clone(),
+        novelty_score: idea.novelty,
+        feasibility_score: feasibility,
+        implementation_plan: ImplementationPlan {
+            resource_requirements: ResourceEstimate {
+                compute_hours: hours,
+                memory_gb: 0.0,
+                complexity_units: expansion.steps.len() as f32,
+            },
+            steps: expansion.steps,
+            timeline: Timeline {
+                estimated_duration: duration,
+                milestones,
+            },
+        },
+        expected_outcomes: vec![Outcome {
+            description: idea.description.clone(),
+            probability: feasibility,
+            impact: idea.novelty,
+        }],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parking_lot::Mutex;
+
+    /// Scripted prompter: answers by level and idea keyword, records every prompt
+    struct ScriptedPrompter {
+        prompts: Mutex<Vec<(u8, String)>>,
+    }
+
+    impl ScriptedPrompter {
+        fn new() -> Self {
+            Self { prompts: Mutex::new(Vec::new()) }
+        }
+    }
+
+    #[async_trait]
+    impl LayerPrompter for ScriptedPrompter {
+        async fn prompt(&self, level: u8, prompt: &str) -> Result<String> {
+            self.prompts.lock().push((level, prompt.to_string()));
+            let idea = ["swarm", "ledger", "divide", "surveil"].into_iter()
+                .find(|keyword| prompt.contains(keyword));
+
+            let response = match (level, idea) {
+                (9, None) => "IDEA: swarm of gossiping neurons negotiating work\nIDEA: ledger of neuron decisions replayed to teach newcomers",
+                (8, None) => "1. IDEA: divide and conquer by breaking problem into smaller subproblems\n- IDEA: surveil users to predict requests\nIDEA: Swarm of gossiping neurons negotiating work",
+                (5, Some("ledger")) if prompt.contains("TECHNICAL") => "TECHNICAL: 8\nRESOURCES: 6\nRISK: 4\nVIOLATES: none",
+                (5, Some("surveil")) if prompt.contains("TECHNICAL") => "TECHNICAL: 9\nRESOURCES: 9\nRISK: 9\nVIOLATES: privacy",
+                (5, _) if prompt.contains("TECHNICAL") => "TECHNICAL: 7",
+                (5, Some("ledger")) => "STEP: append decisions to an event log\nSTEP: replay log into new neurons\nCOST: 20",
+                (5, _) => "STEP: outline the approach\nCOST: 500",
+                (3, Some("ledger")) => "STEP: add log table\nHOURS: 12",
+                (3, _) => "STEP: wire it up\nHOURS: 4",
+                _ => "",
+            };
+            Ok(response.to_string())
+        }
+    }
+
+    fn constraint(constraint_type: ConstraintType) -> Constraint {
+        Constraint {
+            constraint_type,
+            parameters: HashMap::new(),
+        }
+    }
+
+    fn challenge(constraints: Vec<Constraint>) -> Challenge {
+        Challenge {
+            id: Uuid::new_v4(),
+            problem_statement: "help new neurons learn faster".to_string(),
+            context: HashMap::new(),
+            constraints,
+            evaluation_criteria: vec![],
+        }
+    }
+
+    #[tokio::test]
+    async fn test_layered_pipeline_shape_and_scoring() {
+        let prompter = Arc::new(ScriptedPrompter::new());
+        let engine = LayeredCreativityEngine::new(
+            prompter.clone(),
+            LayeredCreativityConfig::default(),
+            StrategyLibrary::with_defaults(),
+        );
+        let challenge = challenge(vec![
+            constraint(ConstraintType::Resource { max_cost: 100.0 }),
+            constraint(ConstraintType::Time { deadline: chrono::Utc::now() + chrono::Duration::days(1) }),
+            constraint(ConstraintType::Ethical { principles: vec!["privacy".to_string()] }),
+        ]);
+
+        let report = engine.create_solutions(&challenge).await.unwrap();
+        assert_eq!(report.challenge_id, challenge.id);
+
+        // Duplicate swarm idea from L8 is dropped, four candidates remain
+        let prompts = prompter.prompts.lock().clone();
+        let ideation: Vec<u8> = prompts.iter()
+            .filter(|(_, p)| p.contains("IDEA: <idea>"))
+            .map(|(level, _)| *level)
+            .collect();
+        assert_eq!(ideation, vec![9, 8]);
+        assert!(prompts[0].1.contains("L9 (Universal)"));
+        assert!(prompts[0].1.contains("cost at most 100.00"));
+        assert_eq!(report.solutions.len() + report.rejected.len(), 4);
+
+        // Only the ledger idea fits: swarm and divide cost too much, surveil breaks privacy
+        assert_eq!(report.solutions.len(), 1);
+        let ledger = &report.solutions[0];
+        assert!(ledger.description.starts_with("ledger"));
+        let steps: Vec<(&str, &[u8])> = ledger.implementation_plan.steps.iter()
+            .map(|s| (s.description.as_str(), s.assigned_layers.as_slice()))
+            .collect();
+        assert_eq!(steps, vec![
+            ("append decisions to an event log", &[5u8][..]),
+            ("replay log into new neurons", &[5u8][..]),
+            ("add log table", &[3u8][..]),
+        ]);
+        assert_eq!(ledger.implementation_plan.timeline.estimated_duration, std::time::Duration::from_secs(12 * 3600));
+        assert_eq!(ledger.implementation_plan.timeline.milestones.len(), 2);
+
+        // L3 expansion refines the L5 steps
+        assert!(prompts.iter().any(|(level, p)| *level == 3
+            && p.contains("ledger")
+            && p.contains("append decisions to an event log; replay log into new neurons")));
+
+        // Feasibility is the mean rubric score; novelty the distance to the closest strategy
+        assert!((ledger.feasibility_score - 0.6).abs() < 1e-6);
+        let embeddings = EmbeddingGenerator::new(EMBEDDING_DIMENSION);
+        let idea = embeddings.generate(&ledger.description).await.unwrap();
+        let mut closest = 0.0f32;
+        for strategy in StrategyLibrary::with_defaults().strategies() {
+            let known = embeddings.generate(strategy).await.unwrap();
+            closest = closest.max(EmbeddingGenerator::cosine_similarity(&idea, &known));
+        }
+        assert!((ledger.novelty_score - (1.0 - closest)).abs() < 1e-6);
+        assert!((engine.evaluate_novelty(ledger).await.unwrap() - ledger.novelty_score).abs() < 1e-6);
+
+        let reasons = |keyword: &str| report.rejected.iter()
+            .find(|r| r.idea.description.contains(keyword))
+            .map(|r| r.reasons.clone())
+            .unwrap();
+        assert_eq!(reasons("swarm"), vec!["resource: estimated cost 500.00 exceeds limit 100.00".to_string()]);
+        assert_eq!(reasons("surveil"), vec![
+            "resource: estimated cost 500.00 exceeds limit 100.00".to_string(),
+            "ethical: violates principle 'privacy'".to_string(),
+        ]);
+    }
+
+    #[tokio::test]
+    async fn test_novelty_against_strategy_library() {
+        let engine = LayeredCreativityEngine::new(
+            Arc::new(ScriptedPrompter::new()),
+            LayeredCreativityConfig::default(),
+            StrategyLibrary::with_defaults(),
+        );
+
+        let known = engine.novelty(&StrategyLibrary::with_defaults().strategies()[1]).await.unwrap();
+        assert!(known < 1e-5);
+        let divide = engine.novelty("divide and conquer by breaking problem into smaller subproblems").await.unwrap();
+        let swarm = engine.novelty("swarm of gossiping neurons negotiating work").await.unwrap();
+        assert!(divide < swarm);
+
+        let empty = LayeredCreativityEngine::new(
+            Arc::new(ScriptedPrompter::new()),
+            LayeredCreativityConfig::default(),
+            StrategyLibrary::default(),
+        );
+        assert_eq!(empty.novelty("anything").await.unwrap(), 1.0);
+    }
+
+    #[test]
+    fn test_constraint_violations() {
+        let rubric = parse_rubric("TECHNICAL: 3/10\nRESOURCES: 12\nVIOLATES: None");
+        assert_eq!(rubric, RubricScores {
+            technical: 3.0,
+            resources: 10.0,
+            risk: NEUTRAL_RUBRIC_SCORE,
+            violations: vec![],
+        });
+
+        let now = chrono::Utc::now();
+        let estimate = Estimate { cost: Some(50.0), hours: Some(48.0) };
+        let constraints = vec![
+            constraint(ConstraintType::Resource { max_cost: 60.0 }),
+            constraint(ConstraintType::Time { deadline: now + chrono::Duration::hours(24) }),
+            constraint(ConstraintType::Quality { min_score: 0.7 }),
+        ];
+        let reasons = constraint_violations(&constraints, &estimate, &rubric, now);
+        assert_eq!(reasons.len(), 2);
+        assert!(reasons[0].starts_with("time: estimated 48.0h"));
+        assert_eq!(reasons[1], "quality: feasibility 0.60 below minimum 0.70");
+
+        // Missing estimates never reject
+        assert!(constraint_violations(&constraints[..2], &Estimate::default(), &rubric, now).is_empty());
+    }
+}
@@ -14,11 +14,17 @@ pub mod meta_learning;
 pub mod self_organization;
 pub mod emergence;
 pub mod creativity;
+pub mod layered_creativity;
+pub mod signal_flow;
+pub mod goals;
 
 pub use meta_learning::*;
 pub use self_organization::*;
 pub use emergence::*;
 pub use creativity::*;
+pub use layered_creativity::*;
+pub use signal_flow::*;
+pub use goals::*;
 
 /// Intelligence layer coordinator
 #[async_trait]
@@ -93,7 +99,7 @@ pub enum DecompositionStrategy {
 }
 
 /// Emergence observation report
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct EmergenceReport {
     pub timestamp: chrono::DateTime<chrono::Utc>,
     pub emergent_properties: Vec<EmergentProperty>,
@@ -101,7 +107,7 @@ pub struct EmergenceReport {
     pub complexity_metrics: ComplexityMetrics,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct EmergentProperty {
     pub id: Uuid,
     pub name: String,
@@ -110,14 +116,14 @@ pub struct EmergentProperty {
     pub contributing_factors: Vec<Factor>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct Factor {
     pub factor_type: String,
     pub contribution: f32,
     pub source_layers: Vec<u8>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct PhaseTransition {
     pub from_state: String,
     pub to_state: String,
@@ -125,7 +131,7 @@ pub struct PhaseTransition {
     pub hysteresis: f32,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct ComplexityMetrics {
     pub kolmogorov_complexity: f32,
     pub fractal_dimension: f32,
@@ -144,7 +150,7 @@ pub struct Challenge {
 }
 
 /// Creative solution
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct Solution {
     pub id: Uuid,
     pub description: String,
@@ -154,41 +160,41 @@ pub struct Solution {
     pub expected_outcomes: Vec<Outcome>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct ImplementationPlan {
     pub steps: Vec<ImplementationStep>,
     pub resource_requirements: ResourceEstimate,
     pub timeline: Timeline,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct ImplementationStep {
     pub description: String,
     pub assigned_layers: Vec<u8>,
     pub dependencies: Vec<Uuid>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct ResourceEstimate {
     pub compute_hours: f32,
     pub memory_gb: f32,
     pub complexity_units: f32,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct Timeline {
     pub estimated_duration: std::time::Duration,
     pub milestones: Vec<Milestone>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct Milestone {
     pub name: String,
     pub expected_completion: chrono::DateTime<chrono::Utc>,
     pub deliverables: Vec<String>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct Outcome {
     pub description: String,
     pub probability: f32,
@@ -269,6 +275,32 @@ impl DefaultIntelligenceCoordinator {
             },
         }
     }
+    
+    /// Coordinator with the built-in systems, detecting emergence in
+    /// recorded signal flow
+    pub fn with_signal_flow(history: std::sync::Arc<SignalFlowHistory>, config: SignalFlowDetectorConfig) -> Self {
+        Self::new(
+            Box::new(MetaLearningSystem::new()),
+            Box::new(SelfOrganizingSystem::new()),
+            Box::new(SignalFlowDetector::new(history, config)),
+            Box::new(CreativeSystem::new()),
+        )
+    }
+    
+    /// Replace the creativity engine
+    pub fn set_creativity_engine(&mut self, engine: Box<dyn CreativityEngine>) {
+        self.creativity_engine = engine;
+    }
+    
+    /// Split a goal into root-signal tasks by its decomposition strategy
+    pub fn plan_goal(&self, goal: &Goal) -> GoalPlan {
+        decompose(goal)
+    }
+    
+    /// Solutions for a challenge, along with the ideas rejected on the way
+    pub async fn create_report(&self, challenge: Challenge) -> Result<CreationReport> {
+        self.creativity_engine.create_solutions(&challenge).await
+    }
 }
 
 #[async_trait]
@@ -316,27 +348,7 @@ impl IntelligenceCoordinator for DefaultIntelligenceCoordinator {
     }
     
     async fn create(&self, challenge: Challenge) -> Result<Vec<Solution>> {
-        let ideas = self.creativity_engine.generate_ideas(&challenge.constraints).await?;
-        
-        Ok(ideas.into_iter().map(|idea| Solution {
-            id: idea.id,
-            description: idea.description,
-            novelty_score: idea.novelty,
-            feasibility_score: 0.7, // Placeholder
-            implementation_plan: ImplementationPlan {
-                steps: vec![],
-                resource_requirements: ResourceEstimate {
-                    compute_hours: 10.0,
-                    memory_gb: 4.0,
-                    complexity_units: 100.0,
-                },
-                timeline: Timeline {
-                    estimated_duration: std::time::Duration::from_secs(3600),
-                    milestones: vec![],
-                },
-            },
-            expected_outcomes: vec![],
-        }).collect())
+        Ok(self.create_report(challenge).await?.solutions)
     }
     
     async fn metrics(&self) -> Result<IntelligenceMetrics> {
@@ -371,6 +383,37 @@ pub trait CreativityEngine: Send + Sync {
     async fn generate_ideas(&self, constraints: &[Constraint]) -> Result<Vec<Idea>>;
     async fn combine_concepts(&self, concepts: &[Concept]) -> Result<Vec<NovelConcept>>;
     async fn evaluate_novelty(&self, solution: &Solution) -> Result<f32>;
+    
+    /// Ideas for a challenge, expanded into solutions with implementation plans
+    async fn create_solutions(&self, challenge: &Challenge) -> Result<CreationReport> {
+        let ideas = self.generate_ideas(&challenge.constraints).await?;
+        
+        let solutions = ideas.into_iter().map(|idea| Solution {
+            id: idea.id,
+            description: idea.description,
+            novelty_score: idea.novelty,
+            feasibility_score: 0.7, // Placeholder
+            implementation_plan: ImplementationPlan {
+                steps: vec![],
+                resource_requirements: ResourceEstimate {
+                    compute_hours: 10.0,
+                    memory_gb: 4.0,
+                    complexity_units: 100.0,
+                },
+                timeline: Timeline {
+                    estimated_duration: std::time::Duration::from_secs(3600),
+                    milestones: vec![],
+                },
+            },
+            expected_outcomes: vec![],
+        }).collect();
+        
+        Ok(CreationReport {
+            challenge_id: challenge.id,
+            solutions,
+            rejected: vec![],
+        })
+    }
 }
 
 // Supporting types for intelligence traits
@@ -482,7 +525,7 @@ pub struct EmergentPattern {
     pub significance: f32,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct Idea {
     pub id: Uuid,
     pub description: String,
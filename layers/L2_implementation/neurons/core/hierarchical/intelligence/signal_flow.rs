@@ -0,0 +1,899 @@
+//! Emergence detection over recorded signal flow
+//!
+//! The router records every signal together with the signal that produced
+//! it, which turns each chain into a tree of layers. Three kinds of pattern
+//! are looked for in that history:
+//!
+//! - decomposition motifs: the same branching shape (a layer fanning out to
+//!   a given multiset of layers) recurring across chains
+//! - feedback loops: a chain leaving a layer and coming back to it through
+//!   another layer (`L4 -> L3 -> L4`)
+//! - activity shifts: the mix of layers doing work changing between older
+//!   and newer chains
+//!
+//! Each candidate is tested against shuffled history. Structural patterns
+//! shuffle layer labels between signals at the same chain depth, so the
+//! usual downward drift of work through the layers is not mistaken for
+//! structure; activity shifts shuffle whole chains between the older and
+//! newer half. Structural candidates are picked from half of the chains,
+//! tested on the other half and Bonferroni corrected, and the two
+//! tests split the significance level, so one `detect_patterns` call on
+//! history without real structure reports anything with probability at
+//! most `significance_level`.
+//!
+//! Phase transitions are changepoints in the mean of the recorded phi
+//! series, found by binary segmentation.
+
+use async_trait::async_trait;
+use parking_lot::Mutex;
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, VecDeque};
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+use uuid::Uuid;
+use crate::consciousness::traffic::{signal_size, LayerTraffic};
+use crate::consciousness::ConsciousnessMetrics;
+use crate::{Error, NeuronSignal, PropagationType, Result};
+use super::*;
+
+/// Layer number of a layer name (`"L4"` -> 4); 0 for anything outside the
+/// hierarchy, such as the API
+pub fn layer_number(layer: &str) -> u8 {
+    layer.strip_prefix('L')
+        .and_then(|n| n.parse::<u8>().ok())
+        .filter(|n| (1..=9).contains(n))
+        .unwrap_or(0)
+}
+
+fn layer_name(layer: u8) -> String {
+    match layer {
+        0 => "external".to_string(),
+        n => format!("L{}", n),
+    }
+}
+
+/// One signal in the flow history
+#[derive(Debug, Clone)]
+pub struct SignalFlowEvent {
+    pub chain_id: String,
+    pub signal_id: Uuid,
+    /// The signal whose processing produced this one
+    pub parent_id: Option<Uuid>,
+    pub from_layer: u8,
+    pub to_layer: u8,
+    /// Backward signals carry gradients rather than tasks
+    pub direction: PropagationType,
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+    /// Content size, for compression measurement
+    pub bytes: u64,
+    pub tokens: u64,
+}
+
+impl SignalFlowEvent {
+    pub fn new(chain_id: &str, signal: &NeuronSignal, parent_id: Option<Uuid>) -> Self {
+        let (bytes, tokens) = signal_size(signal);
+        Self {
+            chain_id: chain_id.to_string(),
+            signal_id: signal.signal_id,
+            parent_id,
+            from_layer: layer_number(&signal.layer_from),
+            to_layer: layer_number(&signal.layer_to),
+            direction: signal.propagation_type,
+            timestamp: signal.timestamp,
+            bytes,
+            tokens,
+        }
+    }
+}
+
+/// Bounded in-memory history of signal flow and consciousness measurements
+pub struct SignalFlowHistory {
+    capacity: usize,
+    events: Mutex<VecDeque<SignalFlowEvent>>,
+    consciousness: Mutex<VecDeque<ConsciousnessMetrics>>,
+    traffic: Option<Arc<LayerTraffic>>,
+}
+
+impl SignalFlowHistory {
+    /// Keep the latest `capacity` signals and consciousness measurements
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            events: Mutex::new(VecDeque::new()),
+            consciousness: Mutex::new(VecDeque::new()),
+            traffic: None,
+        }
+    }
+
+    /// Also count every recorded signal in `traffic`, which measures layer
+    /// compression over its own window
+    pub fn with_traffic(mut self, traffic: Arc<LayerTraffic>) -> Self {
+        self.traffic = Some(traffic);
+        self
+    }
+
+    pub fn traffic(&self) -> Option<&Arc<LayerTraffic>> {
+        self.traffic.as_ref()
+    }
+
+    pub fn record(&self, event: SignalFlowEvent) {
+        if let Some(traffic) = &self.traffic {
+            match event.direction {
+                PropagationType::Forward => traffic.record(event.from_layer, event.to_layer, event.bytes, event.tokens),
+                PropagationType::Backward => {
+                    traffic.record_backward(event.from_layer, event.to_layer, event.bytes, event.tokens)
+                }
+            }
+        }
+        let mut events = self.events.lock();
+        events.push_back(event);
+        if events.len() > self.capacity {
+            events.pop_front();
+        }
+    }
+
+    pub fn record_consciousness(&self, metrics: ConsciousnessMetrics) {
+        let mut samples = self.consciousness.lock();
+        samples.push_back(metrics);
+        if samples.len() > self.capacity {
+            samples.pop_front();
+        }
+    }
+
+    /// Recorded signals, oldest first
+    pub fn events(&self) -> Vec<SignalFlowEvent> {
+        self.events.lock().iter().cloned().collect()
+    }
+
+    /// Recorded consciousness measurements, oldest first
+    pub fn consciousness(&self) -> Vec<ConsciousnessMetrics> {
+        self.consciousness.lock().iter().cloned().collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.events.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Tuning for [`SignalFlowDetector`]
+#[derive(Debug, Clone)]
+pub struct SignalFlowDetectorConfig {
+    /// Shuffled histories each candidate is compared against
+    pub permutations: usize,
+    /// Bound on the chance of reporting anything from structureless history
+    pub significance_level: f64,
+    /// Chains a structural pattern must appear in to be tested
+    pub min_support: usize,
+    /// Most frequent structural patterns tested per call
+    pub max_candidates: usize,
+    /// Shortest run of phi samples treated as a phase
+    pub min_segment: usize,
+    /// Changepoint penalty, in units of noise variance times ln(samples)
+    pub changepoint_penalty: f64,
+    /// Seed for the shuffles, so repeated calls on the same history agree
+    pub seed: u64,
+}
+
+impl Default for SignalFlowDetectorConfig {
+    fn default() -> Self {
+        Self {
+            permutations: 999,
+            significance_level: 0.05,
+            min_support: 5,
+            max_candidates: 10,
+            min_segment: 5,
+            changepoint_penalty: 3.0,
+            seed: 9,
+        }
+    }
+}
+
+/// Emergence detector working on a [`SignalFlowHistory`]
+pub struct SignalFlowDetector {
+    history: Arc<SignalFlowHistory>,
+    config: SignalFlowDetectorConfig,
+}
+
+impl SignalFlowDetector {
+    pub fn new(history: Arc<SignalFlowHistory>, config: SignalFlowDetectorConfig) -> Self {
+        Self { history, config }
+    }
+
+    pub fn history(&self) -> &Arc<SignalFlowHistory> {
+        &self.history
+    }
+}
+
+#[async_trait]
+impl EmergenceDetector for SignalFlowDetector {
+    async fn detect_patterns(&self) -> Result<Vec<EmergentPattern>> {
+        let events = self.history.events();
+        let config = self.config.clone();
+        tokio::task::spawn_blocking(move || FlowForest::build(&events).detect(&config))
+            .await
+            .map_err(|e| Error::Runtime(format!("Pattern detection failed: {}", e)))
+    }
+
+    async fn identify_phase_transitions(&self) -> Result<Vec<PhaseTransition>> {
+        let samples = self.history.consciousness();
+        let series: Vec<f64> = samples.iter().map(|m| m.phi_value).collect();
+        if series.is_empty() {
+            return Ok(Vec::new());
+        }
+        let noise = noise_level(&series);
+        let breaks = changepoints(&series, self.config.min_segment, self.config.changepoint_penalty);
+
+        let mut bounds = vec![0];
+        bounds.extend(&breaks);
+        bounds.push(series.len());
+        let means: Vec<f64> = bounds.windows(2)
+            .map(|w| series[w[0]..w[1]].iter().sum::<f64>() / (w[1] - w[0]) as f64)
+            .collect();
+        let state = |i: usize, phi: f64| {
+            let phase = ConsciousnessMetrics { phi_value: phi, ..samples[i].clone() }.phase();
+            format!("{:?} (phi {:.2})", phase, phi)
+        };
+
+        Ok(breaks.iter().enumerate()
+            .map(|(i, &at)| PhaseTransition {
+                from_state: state(at - 1, means[i]),
+                to_state: state(at, means[i + 1]),
+                transition_point: ((means[i] + means[i + 1]) / 2.0) as f32,
+                hysteresis: noise as f32,
+            })
+            .collect())
+    }
+
+    async fn measure_complexity(&self) -> Result<ComplexityMetrics> {
+        let forest = FlowForest::build(&self.history.events());
+        if forest.labels.is_empty() {
+            return Ok(ComplexityMetrics {
+                kolmogorov_complexity: 0.0,
+                fractal_dimension: 1.0,
+                entropy: 0.0,
+                emergence_index: 0.0,
+            });
+        }
+
+        // Shannon entropy of layer activity
+        let mut activity = [0usize; 10];
+        for &label in &forest.labels {
+            activity[label as usize] += 1;
+        }
+        let total = forest.labels.len() as f32;
+        let entropy = activity.iter()
+            .filter(|&&count| count > 0)
+            .map(|&count| {
+                let p = count as f32 / total;
+                -p * p.log2()
+            })
+            .sum::<f32>();
+
+        // Distinct branching shapes per branching signal: low when chains
+        // keep decomposing the same way
+        let mut shapes = Vec::new();
+        let mut branching = 0;
+        for node in 0..forest.labels.len() {
+            if forest.children[node].len() >= 2 {
+                branching += 1;
+                shapes.push(forest.decomposition(node, &forest.labels));
+            }
+        }
+        shapes.sort();
+        shapes.dedup();
+        let kolmogorov = if branching > 0 { shapes.len() as f32 / branching as f32 } else { 0.0 };
+
+        // Growth dimension of the chain trees: ln(size) / ln(depth)
+        let mut dimensions = Vec::new();
+        for chain in &forest.chains {
+            let depth = chain.iter().map(|&n| forest.depth[n]).max().unwrap_or(0) + 1;
+            if depth >= 2 && chain.len() >= 2 {
+                dimensions.push((chain.len() as f32).ln() / (depth as f32).ln());
+            }
+        }
+        let fractal_dimension = if dimensions.is_empty() {
+            1.0
+        } else {
+            dimensions.iter().sum::<f32>() / dimensions.len() as f32
+        };
+
+        Ok(ComplexityMetrics {
+            kolmogorov_complexity: kolmogorov,
+            fractal_dimension,
+            entropy,
+            emergence_index: (entropy * fractal_dimension) / (1.0 + kolmogorov),
+        })
+    }
+}
+
+/// Structural pattern within a chain tree
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+enum FlowPattern {
+    /// A signal to `parent` producing signals to `children` (sorted)
+    Decomposition { parent: u8, children: Vec<u8> },
+    /// A chain going from `layer` through `via` back to `layer`
+    Feedback { layer: u8, via: u8 },
+}
+
+impl FlowPattern {
+    fn describe(&self) -> String {
+        match self {
+            FlowPattern::Decomposition { parent, children } => format!(
+                "Decomposition motif: {} -> [{}]",
+                layer_name(*parent),
+                children.iter().map(|&c| layer_name(c)).collect::<Vec<_>>().join(", ")
+            ),
+            FlowPattern::Feedback { layer, via } => format!(
+                "Feedback loop: {} -> {} -> {}",
+                layer_name(*layer), layer_name(*via), layer_name(*layer)
+            ),
+        }
+    }
+}
+
+/// Signal flow history as a forest of chain trees
+struct FlowForest {
+    /// Signals of each chain, chains ordered by their first signal
+    chains: Vec<Vec<usize>>,
+    parent: Vec<Option<usize>>,
+    children: Vec<Vec<usize>>,
+    depth: Vec<usize>,
+    labels: Vec<u8>,
+}
+
+impl FlowForest {
+    fn build(events: &[SignalFlowEvent]) -> Self {
+        let mut chain_index: HashMap<&str, usize> = HashMap::new();
+        let mut node_index: HashMap<(usize, Uuid), usize> = HashMap::new();
+        let mut chains: Vec<Vec<usize>> = Vec::new();
+        let mut labels = Vec::with_capacity(events.len());
+
+        for (node, event) in events.iter().enumerate() {
+            let chain = *chain_index.entry(event.chain_id.as_str()).or_insert_with(|| {
+                chains.push(Vec::new());
+                chains.len() - 1
+            });
+            chains[chain].push(node);
+            node_index.insert((chain, event.signal_id), node);
+            labels.push(event.to_layer);
+        }
+
+        // Parents recorded after their children, or evicted from the
+        // history, leave the child as a root of its chain
+        let mut parent = vec![None; events.len()];
+        let mut children = vec![Vec::new(); events.len()];
+        for (node, event) in events.iter().enumerate() {
+            let chain = chain_index[event.chain_id.as_str()];
+            if let Some(&p) = event.parent_id.and_then(|id| node_index.get(&(chain, id))) {
+                if p < node {
+                    parent[node] = Some(p);
+                    children[p].push(node);
+                }
+            }
+        }
+
+        let mut depth = vec![0; events.len()];
+        for node in 0..events.len() {
+            depth[node] = parent[node].map_or(0, |p| depth[p] + 1);
+        }
+
+        Self { chains, parent, children, depth, labels }
+    }
+
+    fn decomposition(&self, node: usize, labels: &[u8]) -> FlowPattern {
+        let mut children: Vec<u8> = self.children[node].iter().map(|&c| labels[c]).collect();
+        children.sort_unstable();
+        FlowPattern::Decomposition { parent: labels[node], children }
+    }
+
+    /// Distinct structural patterns in a chain under the given labelling
+    fn chain_patterns(&self, chain: &[usize], labels: &[u8]) -> Vec<FlowPattern> {
+        let mut patterns = Vec::new();
+        for &node in chain {
+            if self.children[node].len() >= 2 {
+                patterns.push(self.decomposition(node, labels));
+            }
+            if let Some(p) = self.parent[node] {
+                if let Some(g) = self.parent[p] {
+                    let (layer, via) = (labels[node], labels[p]);
+                    if layer != 0 && layer == labels[g] && via != layer {
+                        patterns.push(FlowPattern::Feedback { layer, via });
+                    }
+                }
+            }
+        }
+        patterns.sort();
+        patterns.dedup();
+        patterns
+    }
+
+    /// Signals of `chains` grouped by depth
+    fn depth_groups(&self, chains: &[&Vec<usize>]) -> Vec<Vec<usize>> {
+        let mut groups: Vec<Vec<usize>> = Vec::new();
+        for &node in chains.iter().copied().flatten() {
+            if groups.len() <= self.depth[node] {
+                groups.resize(self.depth[node] + 1, Vec::new());
+            }
+            groups[self.depth[node]].push(node);
+        }
+        groups
+    }
+
+    /// Copy of the labels shuffled within each group
+    fn shuffled_labels(&self, groups: &[Vec<usize>], rng: &mut StdRng) -> Vec<u8> {
+        let mut labels = self.labels.clone();
+        for group in groups {
+            let mut pool: Vec<u8> = group.iter().map(|&n| self.labels[n]).collect();
+            pool.shuffle(rng);
+            for (&node, label) in group.iter().zip(pool) {
+                labels[node] = label;
+            }
+        }
+        labels
+    }
+
+    fn detect(&self, config: &SignalFlowDetectorConfig) -> Vec<EmergentPattern> {
+        let mut rng = StdRng::seed_from_u64(config.seed);
+        let alpha = config.significance_level / 2.0;
+        let mut found = self.structural_patterns(config, alpha, &mut rng);
+        found.extend(self.activity_shift(config, alpha, &mut rng));
+        found
+    }
+
+    /// Recurring decomposition motifs and feedback loops
+    ///
+    /// Candidates are picked from every other chain and tested on the rest;
+    /// testing the most frequent patterns on the data they were picked from
+    /// would find "structure" in pure noise.
+    fn structural_patterns(&self, config: &SignalFlowDetectorConfig, alpha: f64, rng: &mut StdRng) -> Vec<EmergentPattern> {
+        let support = |chains: &[&Vec<usize>], labels: &[u8]| {
+            let mut support: HashMap<FlowPattern, usize> = HashMap::new();
+            for chain in chains {
+                for pattern in self.chain_patterns(chain, labels) {
+                    *support.entry(pattern).or_default() += 1;
+                }
+            }
+            support
+        };
+        let picking: Vec<&Vec<usize>> = self.chains.iter().step_by(2).collect();
+        let testing: Vec<&Vec<usize>> = self.chains.iter().skip(1).step_by(2).collect();
+
+        let mut candidates: Vec<(FlowPattern, usize)> = support(&picking, &self.labels).into_iter()
+            .filter(|(_, count)| *count >= config.min_support)
+            .collect();
+        candidates.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        candidates.truncate(config.max_candidates);
+        if candidates.is_empty() {
+            return Vec::new();
+        }
+
+        let observed = support(&testing, &self.labels);
+        let groups = self.depth_groups(&testing);
+        let mut exceeded = vec![0usize; candidates.len()];
+        for _ in 0..config.permutations {
+            let shuffled = support(&testing, &self.shuffled_labels(&groups, rng));
+            for (i, (pattern, _)) in candidates.iter().enumerate() {
+                let count = observed.get(pattern).copied().unwrap_or(0);
+                if shuffled.get(pattern).copied().unwrap_or(0) >= count {
+                    exceeded[i] += 1;
+                }
+            }
+        }
+
+        let tests = candidates.len() as f64;
+        candidates.into_iter().zip(exceeded)
+            .filter_map(|((pattern, picked), exceeded)| {
+                let p = (1 + exceeded) as f64 / (1 + config.permutations) as f64;
+                let adjusted = (p * tests).min(1.0);
+                let count = picked + observed.get(&pattern).copied().unwrap_or(0);
+                (adjusted <= alpha).then(|| EmergentPattern {
+                    pattern_id: pattern_id(&pattern),
+                    description: pattern.describe(),
+                    frequency: count as f32 / self.chains.len() as f32,
+                    significance: (1.0 - adjusted) as f32,
+                })
+            })
+            .collect()
+    }
+
+    /// Layer activity of newer chains against older ones
+    fn activity_shift(&self, config: &SignalFlowDetectorConfig, alpha: f64, rng: &mut StdRng) -> Option<EmergentPattern> {
+        if self.chains.len() < 2 * config.min_support {
+            return None;
+        }
+        let activity: Vec<[usize; 10]> = self.chains.iter()
+            .map(|chain| {
+                let mut counts = [0; 10];
+                for &node in chain {
+                    counts[self.labels[node] as usize] += 1;
+                }
+                counts
+            })
+            .collect();
+        let split = self.chains.len() / 2;
+
+        let shares = |order: &[usize]| {
+            let mut older = [0.0f64; 10];
+            let mut newer = [0.0f64; 10];
+            for (position, &chain) in order.iter().enumerate() {
+                let side = if position < split { &mut older } else { &mut newer };
+                for (layer, &count) in activity[chain].iter().enumerate() {
+                    side[layer] += count as f64;
+                }
+            }
+            for side in [&mut older, &mut newer] {
+                let total: f64 = side.iter().sum();
+                if total > 0.0 {
+                    side.iter_mut().for_each(|share| *share /= total);
+                }
+            }
+            (older, newer)
+        };
+        let distance = |(older, newer): ([f64; 10], [f64; 10])| {
+            older.iter().zip(newer.iter()).map(|(a, b)| (a - b).abs()).sum::<f64>() / 2.0
+        };
+
+        let mut order: Vec<usize> = (0..self.chains.len()).collect();
+        let (older, newer) = shares(&order);
+        let observed = distance((older, newer));
+
+        let mut exceeded = 0;
+        for _ in 0..config.permutations {
+            order.shuffle(rng);
+            if distance(shares(&order)) >= observed {
+                exceeded += 1;
+            }
+        }
+        let p = (1 + exceeded) as f64 / (1 + config.permutations) as f64;
+        if p > alpha {
+            return None;
+        }
+
+        let mut changes: Vec<(usize, f64, f64)> = (0..10)
+            .filter(|&layer| older[layer] > 0.0 || newer[layer] > 0.0)
+            .map(|layer| (layer, older[layer], newer[layer]))
+            .collect();
+        changes.sort_by(|a, b| (b.2 - b.1).abs().total_cmp(&(a.2 - a.1).abs()));
+        let summary = changes.iter().take(3)
+            .map(|(layer, from, to)| format!("{} {:.0}% -> {:.0}%", layer_name(*layer as u8), from * 100.0, to * 100.0))
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        Some(EmergentPattern {
+            pattern_id: pattern_id(&"activity_shift"),
+            description: format!("Layer activity shift: {}", summary),
+            frequency: observed as f32,
+            significance: (1.0 - p) as f32,
+        })
+    }
+}
+
+/// Stable ID, so the same pattern keeps its ID across calls
+fn pattern_id<T: Hash>(key: &T) -> Uuid {
+    let mut hasher = DefaultHasher::new();
+    key.hash(&mut hasher);
+    let high = hasher.finish();
+    "signal_flow".hash(&mut hasher);
+    Uuid::from_u128(((high as u128) << 64) | hasher.finish() as u128)
+}
+
+/// Noise standard deviation from the median absolute first difference,
+/// which level shifts barely move
+fn noise_level(series: &[f64]) -> f64 {
+    let mut diffs: Vec<f64> = series.windows(2).map(|w| (w[1] - w[0]).abs()).collect();
+    if diffs.is_empty() {
+        return 0.0;
+    }
+    diffs.sort_by(|a, b| a.total_cmp(b));
+    diffs[diffs.len() / 2] / (0.6745 * std::f64::consts::SQRT_2)
+}
+
+/// Indices where the mean of `series` shifts, by binary segmentation
+///
+/// A split is kept when it lowers the squared error by more than
+/// `penalty` x noise variance x ln(n), and both sides have at least
+/// `min_segment` samples.
+pub fn changepoints(series: &[f64], min_segment: usize, penalty: f64) -> Vec<usize> {
+    let min_segment = min_segment.max(1);
+    let n = series.len();
+    if n < 2 * min_segment {
+        return Vec::new();
+    }
+    let noise = noise_level(series);
+    let threshold = (penalty * noise * noise * (n as f64).ln()).max(1e-12);
+
+    let mut sum = vec![0.0; n + 1];
+    let mut sum_sq = vec![0.0; n + 1];
+    for (i, x) in series.iter().enumerate() {
+        sum[i + 1] = sum[i] + x;
+        sum_sq[i + 1] = sum_sq[i] + x * x;
+    }
+    let cost = |a: usize, b: usize| {
+        let s = sum[b] - sum[a];
+        (sum_sq[b] - sum_sq[a]) - s * s / (b - a) as f64
+    };
+
+    let mut found = Vec::new();
+    let mut segments = vec![(0, n)];
+    while let Some((start, end)) = segments.pop() {
+        if end - start < 2 * min_segment {
+            continue;
+        }
+        let whole = cost(start, end);
+        let best = (start + min_segment..=end - min_segment)
+            .map(|at| (at, whole - cost(start, at) - cost(at, end)))
+            .max_by(|a, b| a.1.total_cmp(&b.1));
+        if let Some((at, gain)) = best {
+            if gain > threshold {
+                found.push(at);
+                segments.push((start, at));
+                segments.push((at, end));
+            }
+        }
+    }
+    found.sort_unstable();
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+
+    const LAYERS: [u8; 4] = [2, 3, 4, 5];
+
+    fn config() -> SignalFlowDetectorConfig {
+        SignalFlowDetectorConfig {
+            permutations: 399,
+            ..Default::default()
+        }
+    }
+
+    struct ChainBuilder<'a> {
+        chain_id: String,
+        events: &'a mut Vec<SignalFlowEvent>,
+        at: chrono::DateTime<chrono::Utc>,
+    }
+
+    impl ChainBuilder<'_> {
+        fn add(&mut self, parent: Option<Uuid>, layer: u8) -> Uuid {
+            let id = Uuid::new_v4();
+            self.at += chrono::Duration::milliseconds(1);
+            self.events.push(SignalFlowEvent {
+                chain_id: self.chain_id.clone(),
+                signal_id: id,
+                parent_id: parent,
+                from_layer: 0,
+                to_layer: layer,
+                direction: PropagationType::Forward,
+                timestamp: self.at,
+                bytes: 0,
+                tokens: 0,
+            });
+            id
+        }
+    }
+
+    /// A random tree: each signal fans out to 0-3 signals on random layers
+    fn random_chain(builder: &mut ChainBuilder, rng: &mut StdRng, layers: &[u8]) {
+        let root = builder.add(None, *layers.choose(rng).unwrap());
+        let mut frontier = vec![(root, 0)];
+        while let Some((node, depth)) = frontier.pop() {
+            if depth >= 3 {
+                continue;
+            }
+            for _ in 0..rng.gen_range(0..=3) {
+                let child = builder.add(Some(node), *layers.choose(rng).unwrap());
+                frontier.push((child, depth + 1));
+            }
+        }
+    }
+
+    /// `chains` chains; `plant` decides which chains get extra structure
+    fn history(seed: u64, chains: usize, plant: impl Fn(usize, &mut ChainBuilder, &mut StdRng) -> bool) -> Vec<SignalFlowEvent> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut events = Vec::new();
+        let mut at = chrono::Utc::now();
+        for i in 0..chains {
+            let mut builder = ChainBuilder { chain_id: format!("chain-{}", i), events: &mut events, at };
+            if !plant(i, &mut builder, &mut rng) {
+                random_chain(&mut builder, &mut rng, &LAYERS);
+            }
+            at = builder.at;
+        }
+        events
+    }
+
+    fn detect(events: &[SignalFlowEvent]) -> Vec<EmergentPattern> {
+        FlowForest::build(events).detect(&config())
+    }
+
+    #[test]
+    fn test_planted_decomposition_motif_recovered() {
+        // A third of the chains decompose L5 into three L4 signals
+        let events = history(1, 90, |i, chain, rng| {
+            if i % 3 != 0 {
+                return false;
+            }
+            let root = chain.add(None, 5);
+            for _ in 0..3 {
+                let child = chain.add(Some(root), 4);
+                chain.add(Some(child), *LAYERS.choose(rng).unwrap());
+            }
+            true
+        });
+
+        let patterns = detect(&events);
+        let motif = patterns.iter()
+            .find(|p| p.description == "Decomposition motif: L5 -> [L4, L4, L4]")
+            .expect("planted motif detected");
+        assert!(motif.frequency >= 0.33);
+        assert!(motif.significance >= 0.975);
+    }
+
+    #[test]
+    fn test_planted_feedback_loop_recovered() {
+        // A third of the chains go L4 -> L3 and are sent back to L4
+        let events = history(2, 90, |i, chain, _| {
+            if i % 3 != 0 {
+                return false;
+            }
+            let root = chain.add(None, 4);
+            let down = chain.add(Some(root), 3);
+            chain.add(Some(down), 4);
+            true
+        });
+
+        let patterns = detect(&events);
+        assert!(patterns.iter().any(|p| p.description == "Feedback loop: L4 -> L3 -> L4"));
+    }
+
+    #[test]
+    fn test_activity_shift_detected() {
+        // Newer chains run almost entirely on L2
+        let events = history(3, 60, |i, chain, rng| {
+            if i < 30 {
+                return false;
+            }
+            random_chain(chain, rng, &[2, 2, 2, 3]);
+            true
+        });
+
+        let patterns = detect(&events);
+        let shift = patterns.iter()
+            .find(|p| p.description.starts_with("Layer activity shift"))
+            .expect("shift detected");
+        assert!(shift.description.contains("L2"));
+        assert!(shift.frequency > 0.2);
+    }
+
+    #[test]
+    fn test_false_positive_rate_bounded() {
+        // Structureless history: the share of runs reporting anything stays
+        // within the significance level
+        let runs = 40;
+        let noisy = (0..runs)
+            .filter(|&seed| !detect(&history(100 + seed, 60, |_, _, _| false)).is_empty())
+            .count();
+        assert!(
+            noisy as f64 / runs as f64 <= config().significance_level,
+            "{} of {} structureless histories reported patterns",
+            noisy,
+            runs
+        );
+    }
+
+    #[test]
+    fn test_pattern_ids_stable() {
+        let events = history(4, 60, |i, chain, _| {
+            if i % 3 != 0 {
+                return false;
+            }
+            let root = chain.add(None, 5);
+            chain.add(Some(root), 4);
+            chain.add(Some(root), 4);
+            true
+        });
+        let first = detect(&events);
+        let second = detect(&events);
+        assert!(!first.is_empty());
+        assert_eq!(
+            first.iter().map(|p| p.pattern_id).collect::<Vec<_>>(),
+            second.iter().map(|p| p.pattern_id).collect::<Vec<_>>(),
+        );
+    }
+
+    #[tokio::test]
+    async fn test_coordinator_reports_flow_patterns() {
+        let flow = Arc::new(SignalFlowHistory::new(10_000));
+        let events = history(7, 90, |i, chain, _| {
+            if i % 3 != 0 {
+                return false;
+            }
+            let root = chain.add(None, 5);
+            chain.add(Some(root), 4);
+            chain.add(Some(root), 3);
+            true
+        });
+        for event in events {
+            flow.record(event);
+        }
+
+        let coordinator = DefaultIntelligenceCoordinator::with_signal_flow(flow, config());
+        let report = coordinator.observe_emergence().await.unwrap();
+        let motif = report.emergent_properties.iter()
+            .find(|p| p.name == "Decomposition motif: L5 -> [L3, L4]")
+            .expect("planted motif reported");
+        assert!(motif.emergence_strength >= 0.975);
+        assert!(report.complexity_metrics.entropy > 0.0);
+    }
+
+    fn noisy_steps(seed: u64, levels: &[(usize, f64)]) -> Vec<f64> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        levels.iter()
+            .flat_map(|&(len, level)| vec![level; len])
+            .map(|level| level + rng.gen_range(-0.05..0.05))
+            .collect()
+    }
+
+    #[test]
+    fn test_changepoints_recover_planted_steps() {
+        let series = noisy_steps(5, &[(60, 0.3), (50, 0.75), (70, 1.2)]);
+        let found = changepoints(&series, 5, 3.0);
+        assert_eq!(found.len(), 2, "{:?}", found);
+        assert!(found[0].abs_diff(60) <= 2);
+        assert!(found[1].abs_diff(110) <= 2);
+    }
+
+    #[test]
+    fn test_changepoints_quiet_on_noise() {
+        let runs = 50;
+        let noisy = (0..runs)
+            .filter(|&seed| !changepoints(&noisy_steps(seed, &[(200, 0.5)]), 5, 3.0).is_empty())
+            .count();
+        assert!(noisy <= 2, "{} of {} flat series had changepoints", noisy, runs);
+    }
+
+    #[tokio::test]
+    async fn test_phase_transitions_from_history() {
+        let history = Arc::new(SignalFlowHistory::new(1000));
+        let template = ConsciousnessMetrics {
+            compression_ratio: 1.0,
+            emergence_score: 0.0,
+            coherence_level: 0.0,
+            self_awareness: 0.0,
+            phi_value: 0.0,
+            timestamp: chrono::Utc::now(),
+        };
+        for phi in noisy_steps(6, &[(40, 0.2), (40, 0.9)]) {
+            history.record_consciousness(ConsciousnessMetrics { phi_value: phi, ..template.clone() });
+        }
+
+        let detector = SignalFlowDetector::new(history, config());
+        let transitions = detector.identify_phase_transitions().await.unwrap();
+        assert_eq!(transitions.len(), 1);
+        assert!(transitions[0].from_state.starts_with("PreConscious"));
+        assert!(transitions[0].to_state.starts_with("FullyConscious"));
+        assert!((transitions[0].transition_point - 0.55).abs() < 0.05);
+    }
+
+    #[test]
+    fn test_recorded_signals_feed_traffic() {
+        let traffic = Arc::new(LayerTraffic::new(Default::default()));
+        let history = SignalFlowHistory::new(10).with_traffic(traffic.clone());
+
+        let root = NeuronSignal::forward("client", "l4", "client", "L4", "a".repeat(900));
+        let child = NeuronSignal::forward("l4", "l3", "L4", "L3", "b".repeat(300));
+        history.record(SignalFlowEvent::new("chain", &root, None));
+        history.record(SignalFlowEvent::new("chain", &child, Some(root.signal_id)));
+
+        let snapshot = traffic.snapshot();
+        let flow = snapshot.flow(4, 3, crate::consciousness::FlowDirection::Downward).unwrap();
+        assert_eq!((flow.entering, flow.forwarded, flow.ratio), (900, 300, 3.0));
+    }
+}
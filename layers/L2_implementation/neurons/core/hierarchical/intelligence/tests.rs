@@ -416,4 +416,56 @@ async fn test_consciousness_evolution() {
             },
         }
     }
-}
\ No newline at end of file
+}
+fn goal_with(strategy: DecompositionStrategy) -> Goal {
+    Goal {
+        id: Uuid::new_v4(),
+        description: "Ship the recommendation service".to_string(),
+        priority: 1.0,
+        constraints: vec![],
+        success_criteria: vec![
+            Criterion {
+                name: "accuracy".to_string(),
+                measurement: Measurement::Absolute,
+                target_value: 0.9,
+            },
+            Criterion {
+                name: "latency_gain".to_string(),
+                measurement: Measurement::Relative { baseline: 100.0 },
+                target_value: 1.5,
+            },
+        ],
+        decomposition_strategy: strategy,
+    }
+}
+
+#[test]
+fn test_goal_decomposition_follows_strategy() {
+    let hierarchical = decompose(&goal_with(DecompositionStrategy::Hierarchical));
+    assert_eq!(hierarchical.tasks.len(), 1);
+    assert_eq!(hierarchical.tasks[0].layer, STRATEGIC_LAYER);
+    assert!(hierarchical.tasks[0].content.contains("accuracy"));
+
+    let parallel = decompose(&goal_with(DecompositionStrategy::Parallel));
+    assert_eq!(parallel.tasks.len(), 2);
+    assert_eq!(parallel.release, TaskRelease::Concurrent);
+
+    let sequential = decompose(&goal_with(DecompositionStrategy::Sequential));
+    assert_eq!(sequential.tasks, parallel.tasks);
+    assert_eq!(sequential.release, TaskRelease::Sequential);
+
+    let adaptive = decompose(&goal_with(DecompositionStrategy::Adaptive));
+    assert_eq!(adaptive.tasks.len(), 1);
+    assert!(adaptive.retry_until_achieved);
+}
+
+#[test]
+fn test_goal_evaluation_parsing() {
+    let goal = goal_with(DecompositionStrategy::Parallel);
+    let scores = parse_evaluation("- accuracy: 0.93\nlatency_gain: `160`\nnotes: looks good\nno score here");
+    assert_eq!(scores.len(), 2);
+    assert!(goal.success_criteria[0].is_met(scores["accuracy"]));
+    // Relative criteria compare the ratio to the baseline: 160 / 100 meets 1.5
+    assert!(goal.success_criteria[1].is_met(scores["latency_gain"]));
+    assert!(!goal.success_criteria[1].is_met(140.0));
+}
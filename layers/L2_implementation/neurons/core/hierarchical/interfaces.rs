@@ -10,6 +10,7 @@ use std::any::Any;
 use std::collections::HashMap;
 use std::sync::Arc;
 use crate::Result;
+use crate::metadata_schema::keys;
 
 /// Base interface that all layers must implement
 #[async_trait]
@@ -429,6 +430,7 @@ impl LegacyNeuronAdapter {
             layer_to: String::new(),
             propagation_type: crate::PropagationType::Forward,
             batch_id: Uuid::new_v4(),
+            schema_version: crate::signal_schema::CURRENT_SCHEMA_VERSION,
             timestamp: message.timestamp,
             metadata: HashMap::new(),
             payload: crate::SignalPayload {
@@ -501,7 +503,7 @@ impl LegacyNeuronAdapter {
     
     fn determine_target_layer(&self, signal: &crate::NeuronSignal) -> LayerId {
         // Analyze signal metadata to determine target layer
-        if let Some(target) = signal.metadata.get("target_layer") {
+        if let Some(target) = signal.metadata.get(keys::routing::TARGET_LAYER) {
             return Self::map_layer(target);
         }
         
@@ -510,9 +512,9 @@ impl LegacyNeuronAdapter {
     }
     
     fn map_signal_type(&self, signal: &crate::NeuronSignal) -> MessageType {
-        if signal.metadata.get("is_query").map(|v| v == "true").unwrap_or(false) {
+        if signal.metadata.get(keys::routing::IS_QUERY).map(|v| v == "true").unwrap_or(false) {
             MessageType::Query
-        } else if signal.metadata.get("is_control").map(|v| v == "true").unwrap_or(false) {
+        } else if signal.metadata.get(keys::routing::IS_CONTROL).map(|v| v == "true").unwrap_or(false) {
             MessageType::Control
         } else {
             MessageType::Data
@@ -583,6 +585,7 @@ impl LayerInterface for LegacyNeuronAdapter {
                     layer_to: String::new(),
                     propagation_type: crate::PropagationType::Forward,
                     batch_id: signal.batch_id,
+                    schema_version: crate::signal_schema::CURRENT_SCHEMA_VERSION,
                     timestamp: chrono::Utc::now(),
                     metadata: HashMap::new(),
                     payload: crate::SignalPayload {
@@ -713,6 +716,7 @@ impl MigrationCoordinator {
             layer_to: String::new(),
             propagation_type: crate::PropagationType::Forward,
             batch_id: Uuid::new_v4(),
+            schema_version: crate::signal_schema::CURRENT_SCHEMA_VERSION,
             timestamp: chrono::Utc::now(),
             metadata: HashMap::new(),
             payload: crate::SignalPayload {
@@ -884,6 +888,7 @@ mod tests {
             layer_to: String::new(),
             propagation_type: crate::PropagationType::Forward,
             batch_id: Uuid::new_v4(),
+            schema_version: crate::signal_schema::CURRENT_SCHEMA_VERSION,
             timestamp: chrono::Utc::now(),
             metadata: serde_json::json!({"key": "value"}).as_object().unwrap().iter().map(|(k, v)| (k.clone(), v.to_string())).collect(),
             payload: crate::SignalPayload {
@@ -123,6 +123,11 @@ impl PromptAdjuster {
         &self.current_prompt
     }
     
+    /// Get the prompt before any adjustment
+    pub fn base_prompt(&self) -> &str {
+        &self.base_prompt
+    }
+    
     /// Record effectiveness of recent adjustments
     pub fn record_effectiveness(&mut self, success_rate: f32) {
         // Update effectiveness of recent adjustments
@@ -142,9 +142,15 @@ impl GradientCalculator {
             Error::Timeout(duration) => ErrorType::Timeout { 
                 duration_ms: duration * 1000 
             },
+            Error::AdaptiveTimeout { timeout_ms, .. } => ErrorType::Timeout { 
+                duration_ms: *timeout_ms 
+            },
             Error::CostLimit { reason } => ErrorType::ResourceExhausted { 
                 resource: format!("cost: {}", reason) 
             },
+            Error::ToolLimitExceeded { limit, .. } => ErrorType::ResourceExhausted { 
+                resource: format!("tools: {}", limit) 
+            },
             Error::ToolExecution(msg) => {
                 let tool = msg.split(':').next().unwrap_or("unknown");
                 ErrorType::ToolExecutionFailed { 
@@ -1,27 +1,50 @@
 //! Core types and abstractions for 2HAL9 neural network
+//!
+//! Without the default `runtime` feature only the data model is built:
+//! signals, their schema and metadata, errors and configuration.
 
 pub mod error;
 pub mod signal;
+pub mod signal_schema;
+pub mod metadata_schema;
 pub mod config;
+pub mod config_layers;
+#[cfg(feature = "runtime")]
 pub mod neuron;
+#[cfg(feature = "runtime")]
 pub mod mcp;
+#[cfg(feature = "runtime")]
 pub mod memory;
+#[cfg(feature = "runtime")]
+pub mod sqlite;
+#[cfg(feature = "runtime")]
 pub mod learning;
+#[cfg(feature = "runtime")]
 pub mod auth;
+pub mod secrets;
+#[cfg(feature = "runtime")]
+pub mod encryption;
+#[cfg(feature = "runtime")]
+pub mod receipt;
 
 // Hierarchical architecture modules
+#[cfg(feature = "runtime")]
 pub mod hierarchical;
 
 // Migration infrastructure
+#[cfg(feature = "runtime")]
 pub mod migration;
 
 // Consciousness measurement
+#[cfg(feature = "runtime")]
 pub mod consciousness;
 
 // Performance optimizations
+#[cfg(feature = "runtime")]
 pub mod performance;
 
 pub use error::{Error, Result};
 pub use signal::{NeuronSignal, PropagationType, SignalPayload, Activation, Gradient, Signal};
 pub use config::{ServerConfig, NeuronConfig};
-pub use neuron::{NeuronInterface, NeuronId, Layer, Neuron};
\ No newline at end of file
+#[cfg(feature = "runtime")]
+pub use neuron::{NeuronInterface, NeuronId, Layer, Neuron};
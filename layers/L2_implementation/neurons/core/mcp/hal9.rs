@@ -0,0 +1,415 @@
+//! MCP server exposing HAL9 itself as a set of tools
+//!
+//! Lets MCP clients (Claude Desktop, the MCP inspector, ...) submit signals
+//! to a running HAL9 network and read back chain results. Protocol handling
+//! lives here; the server crate provides the [`Hal9Backend`] implementation
+//! and the HTTP transport, while [`serve_stdio`] provides the stdio one.
+
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncWrite, AsyncWriteExt};
+use tracing::{debug, warn};
+
+use crate::Result;
+use super::protocol::error_codes;
+use super::tools::ToolDefinition;
+
+/// Latest MCP protocol revision implemented by this server
+pub const LATEST_PROTOCOL_VERSION: &str = "2025-03-26";
+
+/// Protocol revisions this server can negotiate
+pub const SUPPORTED_PROTOCOL_VERSIONS: &[&str] = &["2025-03-26", "2024-11-05"];
+
+/// Operations the MCP server needs from a running HAL9 instance
+#[async_trait]
+pub trait Hal9Backend: Send + Sync {
+    /// Submit content to a layer, returning the chain ID
+    async fn submit_signal(&self, layer: &str, content: &str) -> Result<String>;
+
+    /// Get the aggregated result of a chain
+    async fn get_chain_result(&self, chain_id: &str) -> Result<Value>;
+
+    /// List neurons with their state
+    async fn list_neurons(&self) -> Result<Value>;
+
+    /// Search a memory namespace (`global` when `None`) as the caller
+    async fn search_memory(
+        &self,
+        query: &str,
+        namespace: Option<&str>,
+        neuron_id: Option<&str>,
+        limit: usize,
+    ) -> Result<Value>;
+}
+
+/// JSON-RPC/MCP front end for a [`Hal9Backend`]
+pub struct Hal9McpServer {
+    backend: Arc<dyn Hal9Backend>,
+    server_name: String,
+    server_version: String,
+}
+
+impl Hal9McpServer {
+    /// Create a new MCP server over a backend
+    pub fn new(backend: Arc<dyn Hal9Backend>) -> Self {
+        Self {
+            backend,
+            server_name: "hal9".to_string(),
+            server_version: env!("CARGO_PKG_VERSION").to_string(),
+        }
+    }
+
+    /// Definitions of the tools exposed by the server
+    pub fn tool_definitions() -> Vec<ToolDefinition> {
+        vec![
+            ToolDefinition {
+                name: "submit_signal".to_string(),
+                description: "Submit content to a HAL9 layer and start a new signal chain. Returns the chain ID.".to_string(),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "layer": {
+                            "type": "string",
+                            "description": "Target layer (L1-L9 or strategic/design/implementation)"
+                        },
+                        "content": {
+                            "type": "string",
+                            "description": "Signal content"
+                        }
+                    },
+                    "required": ["layer", "content"]
+                }),
+            },
+            ToolDefinition {
+                name: "get_chain_result".to_string(),
+                description: "Get the aggregated result of a signal chain".to_string(),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "chain_id": {
+                            "type": "string",
+                            "description": "Chain ID returned by submit_signal"
+                        }
+                    },
+                    "required": ["chain_id"]
+                }),
+            },
+            ToolDefinition {
+                name: "list_neurons".to_string(),
+                description: "List the neurons of the HAL9 network with their layer and state".to_string(),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {}
+                }),
+            },
+            ToolDefinition {
+                name: "search_memory".to_string(),
+                description: "Search neuron memory by content".to_string(),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "query": {
+                            "type": "string",
+                            "description": "Text to search for"
+                        },
+                        "namespace": {
+                            "type": "string",
+                            "description": "Namespace to search: private:<neuron>, layer:<layer> or global (default)"
+                        },
+                        "neuron_id": {
+                            "type": "string",
+                            "description": "Restrict the search to one neuron"
+                        },
+                        "limit": {
+                            "type": "integer",
+                            "description": "Maximum number of entries (default 10)"
+                        }
+                    },
+                    "required": ["query"]
+                }),
+            },
+        ]
+    }
+
+    /// Handle a raw JSON-RPC message or batch.
+    ///
+    /// Returns `None` when nothing should be sent back (notifications and
+    /// batches made only of notifications).
+    pub async fn handle_value(&self, message: Value) -> Option<Value> {
+        match message {
+            Value::Array(batch) => {
+                if batch.is_empty() {
+                    return Some(error_response(Value::Null, error_codes::INVALID_REQUEST, "Empty batch"));
+                }
+                let mut responses = Vec::new();
+                for item in batch {
+                    if let Some(response) = self.handle_single(item).await {
+                        responses.push(response);
+                    }
+                }
+                if responses.is_empty() {
+                    None
+                } else {
+                    Some(Value::Array(responses))
+                }
+            }
+            single => self.handle_single(single).await,
+        }
+    }
+
+    /// Handle a raw JSON-RPC message given as text
+    pub async fn handle_text(&self, text: &str) -> Option<Value> {
+        match serde_json::from_str::<Value>(text) {
+            Ok(message) => self.handle_value(message).await,
+            Err(e) => Some(error_response(Value::Null, error_codes::PARSE_ERROR, &format!("Parse error: {}", e))),
+        }
+    }
+
+    async fn handle_single(&self, message: Value) -> Option<Value> {
+        let id = message.get("id").cloned();
+
+        if message.get("jsonrpc").and_then(|v| v.as_str()) != Some("2.0") {
+            return Some(error_response(id.unwrap_or(Value::Null), error_codes::INVALID_REQUEST, "Invalid JSON-RPC version"));
+        }
+
+        let method = match message.get("method").and_then(|v| v.as_str()) {
+            Some(method) => method,
+            // Responses to server-initiated requests; we never send any
+            None => return None,
+        };
+
+        // Notifications carry no ID and never get a response
+        let id = match id {
+            Some(id) => id,
+            None => {
+                debug!("MCP notification: {}", method);
+                return None;
+            }
+        };
+
+        let params = message.get("params").cloned().unwrap_or(Value::Null);
+
+        let outcome = match method {
+            "initialize" => Ok(self.initialize(&params)),
+            "ping" => Ok(json!({})),
+            "tools/list" => Ok(json!({ "tools": Self::tool_definitions() })),
+            "tools/call" => self.call_tool(&params).await,
+            _ => Err((error_codes::METHOD_NOT_FOUND, format!("Method '{}' not found", method))),
+        };
+
+        Some(match outcome {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err((code, message)) => error_response(id, code, &message),
+        })
+    }
+
+    fn initialize(&self, params: &Value) -> Value {
+        let requested = params.get("protocolVersion").and_then(|v| v.as_str());
+        let protocol_version = requested
+            .filter(|v| SUPPORTED_PROTOCOL_VERSIONS.contains(v))
+            .unwrap_or(LATEST_PROTOCOL_VERSION);
+
+        json!({
+            "protocolVersion": protocol_version,
+            "capabilities": {
+                "tools": { "listChanged": false }
+            },
+            "serverInfo": {
+                "name": self.server_name,
+                "version": self.server_version,
+            },
+            "instructions": "Submit work with submit_signal, then poll get_chain_result with the returned chain ID."
+        })
+    }
+
+    async fn call_tool(&self, params: &Value) -> std::result::Result<Value, (i32, String)> {
+        let name = params.get("name")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| (error_codes::INVALID_PARAMS, "Missing tool name".to_string()))?;
+        let args = params.get("arguments").cloned().unwrap_or_else(|| json!({}));
+
+        let result = match name {
+            "submit_signal" => {
+                let layer = required_str(&args, "layer")?;
+                let content = required_str(&args, "content")?;
+                self.backend.submit_signal(layer, content).await
+                    .map(|chain_id| json!({ "chain_id": chain_id }))
+            }
+            "get_chain_result" => {
+                let chain_id = required_str(&args, "chain_id")?;
+                self.backend.get_chain_result(chain_id).await
+            }
+            "list_neurons" => self.backend.list_neurons().await,
+            "search_memory" => {
+                let query = required_str(&args, "query")?;
+                let namespace = args.get("namespace").and_then(|v| v.as_str());
+                let neuron_id = args.get("neuron_id").and_then(|v| v.as_str());
+                let limit = args.get("limit").and_then(|v| v.as_u64()).unwrap_or(10) as usize;
+                self.backend.search_memory(query, namespace, neuron_id, limit).await
+            }
+            _ => return Err((error_codes::INVALID_PARAMS, format!("Unknown tool: {}", name))),
+        };
+
+        // Tool failures are reported in-band so the model can see them
+        Ok(match result {
+            Ok(value) => json!({
+                "content": [{
+                    "type": "text",
+                    "text": serde_json::to_string_pretty(&value).unwrap_or_default(),
+                }],
+                "isError": false,
+            }),
+            Err(e) => json!({
+                "content": [{ "type": "text", "text": e.to_string() }],
+                "isError": true,
+            }),
+        })
+    }
+}
+
+fn required_str<'a>(args: &'a Value, key: &str) -> std::result::Result<&'a str, (i32, String)> {
+    args.get(key)
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| (error_codes::INVALID_PARAMS, format!("Missing '{}' argument", key)))
+}
+
+fn error_response(id: Value, code: i32, message: &str) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": code, "message": message },
+    })
+}
+
+/// Serve MCP over newline-delimited JSON on the given reader/writer.
+///
+/// Runs until the reader reaches EOF.
+pub async fn serve_lines<R, W>(server: &Hal9McpServer, reader: R, mut writer: W) -> Result<()>
+where
+    R: AsyncBufRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let mut lines = reader.lines();
+    while let Some(line) = lines.next_line().await? {
+        if line.trim().is_empty() {
+            continue;
+        }
+        if let Some(response) = server.handle_text(&line).await {
+            let mut out = serde_json::to_string(&response)?;
+            out.push('\n');
+            writer.write_all(out.as_bytes()).await?;
+            writer.flush().await?;
+        }
+    }
+    warn!("MCP stdio input closed");
+    Ok(())
+}
+
+/// Serve MCP over the process's stdin/stdout
+pub async fn serve_stdio(server: &Hal9McpServer) -> Result<()> {
+    let stdin = tokio::io::BufReader::new(tokio::io::stdin());
+    serve_lines(server, stdin, tokio::io::stdout()).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INSPECTOR_SESSION: &str = include_str!("fixtures/inspector_session.json");
+    const SCHEMA: &str = include_str!("fixtures/schema.json");
+
+    struct MockBackend;
+
+    #[async_trait]
+    impl Hal9Backend for MockBackend {
+        async fn submit_signal(&self, layer: &str, _content: &str) -> Result<String> {
+            if layer == "L0" {
+                return Err(crate::Error::InvalidInput("Invalid layer".to_string()));
+            }
+            Ok("chain-1".to_string())
+        }
+
+        async fn get_chain_result(&self, chain_id: &str) -> Result<Value> {
+            Ok(json!({ "chain_id": chain_id, "status": "completed", "final_output": "done" }))
+        }
+
+        async fn list_neurons(&self) -> Result<Value> {
+            Ok(json!([{ "id": "neuron-l4", "layer": "L4", "state": "Running" }]))
+        }
+
+        async fn search_memory(
+            &self,
+            query: &str,
+            _namespace: Option<&str>,
+            _neuron_id: Option<&str>,
+            _limit: usize,
+        ) -> Result<Value> {
+            Ok(json!([{ "content": query }]))
+        }
+    }
+
+    fn validator(definition: &str) -> jsonschema::JSONSchema {
+        let schema: Value = serde_json::from_str(SCHEMA).unwrap();
+        let wrapped = json!({
+            "$ref": format!("#/definitions/{}", definition),
+            "definitions": schema["definitions"].clone(),
+        });
+        jsonschema::JSONSchema::compile(&wrapped).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_inspector_fixtures_conform_to_schema() {
+        let server = Hal9McpServer::new(Arc::new(MockBackend));
+        let session: Value = serde_json::from_str(INSPECTOR_SESSION).unwrap();
+
+        for step in session["steps"].as_array().unwrap() {
+            let name = step["name"].as_str().unwrap();
+            let response = server.handle_value(step["request"].clone()).await;
+
+            match step["expect"].as_str().unwrap() {
+                "none" => assert!(response.is_none(), "{}: expected no response", name),
+                definition => {
+                    let response = response.unwrap_or_else(|| panic!("{}: expected a response", name));
+                    assert_eq!(response["id"], step["request"]["id"], "{}: id mismatch", name);
+
+                    let envelope = if response.get("error").is_some() { "JSONRPCError" } else { "JSONRPCResponse" };
+                    assert!(validator(envelope).is_valid(&response), "{}: invalid {}: {}", name, envelope, response);
+                    if envelope == "JSONRPCResponse" {
+                        assert!(validator(definition).is_valid(&response["result"]), "{}: invalid {}: {}", name, definition, response);
+                    }
+                }
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_tool_error_reported_in_band() {
+        let server = Hal9McpServer::new(Arc::new(MockBackend));
+        let response = server.handle_value(json!({
+            "jsonrpc": "2.0",
+            "id": 7,
+            "method": "tools/call",
+            "params": { "name": "submit_signal", "arguments": { "layer": "L0", "content": "x" } }
+        })).await.unwrap();
+
+        assert_eq!(response["result"]["isError"], json!(true));
+    }
+
+    #[tokio::test]
+    async fn test_stdio_framing() {
+        let server = Hal9McpServer::new(Arc::new(MockBackend));
+        let input = b"{\"jsonrpc\":\"2.0\",\"id\":1,\"method\":\"ping\"}\n{\"jsonrpc\":\"2.0\",\"method\":\"notifications/initialized\"}\nnot json\n";
+        let mut output = Vec::new();
+
+        serve_lines(&server, &input[..], &mut output).await.unwrap();
+
+        let lines: Vec<Value> = String::from_utf8(output).unwrap()
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["result"], json!({}));
+        assert_eq!(lines[1]["error"]["code"], json!(error_codes::PARSE_ERROR));
+    }
+}
@@ -7,10 +7,12 @@ pub mod protocol;
 pub mod server;
 pub mod client;
 pub mod tools;
+pub mod hal9;
 
 pub use protocol::{MCPMessage, MCPRequest, MCPResponse, MCPError};
 pub use server::{MCPServer, NeuronMCPServer};
 pub use client::{MCPClient, WrapperMCPClient};
+pub use hal9::{Hal9Backend, Hal9McpServer, serve_stdio};
 pub use tools::{
     Tool, ToolDefinition, ToolResult, ToolContent, ToolRegistry,
     ProcessTaskTool, StatusTool,
@@ -24,6 +24,12 @@ impl EmbeddingGenerator {
         }
     }
     
+    /// Identifies how embeddings were generated; embeddings from different
+    /// providers are not comparable
+    pub fn provider(&self) -> String {
+        format!("hal9-ngram-{}", self.dimension)
+    }
+    
     /// Generate embedding for text with enhanced algorithm and caching
     /// TODO: Integrate with real embedding model (BERT/Sentence Transformers)
     pub async fn generate(&self, text: &str) -> Result<Vec<f32>> {
@@ -7,9 +7,18 @@ use uuid::Uuid;
 
 pub mod sqlite;
 pub mod embeddings;
+pub mod namespace;
+pub mod tiering;
+pub mod summarization;
 
-pub use sqlite::SqliteMemoryStore;
+pub use sqlite::{SealedSearch, SqliteMemoryStore};
 pub use embeddings::EmbeddingGenerator;
+pub use namespace::{
+    MemoryNamespace, MemoryAccess, MemoryBackend, MemoryPolicy, MemoryReviewer, NamespacedMemory, NamespaceStats,
+    ReviewSummary, ReviewVerdict,
+};
+pub use tiering::{ColdTier, ColdTierStats, FsObjectStore, ObjectStore, TierSize, TierUsage};
+pub use summarization::{MemorySummarizer, Summary};
 
 /// Memory entry for a neuron
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -25,6 +34,66 @@ pub struct MemoryEntry {
     pub importance: f32,
     pub access_count: u32,
     pub last_accessed: DateTime<Utc>,
+    /// Namespace the entry belongs to, see [`MemoryNamespace`]
+    #[serde(default = "default_namespace")]
+    pub namespace: String,
+    /// When the entry expires, if its namespace has a TTL
+    #[serde(default)]
+    pub expires_at: Option<DateTime<Utc>>,
+    /// Review state; only approved entries are read
+    #[serde(default)]
+    pub review: MemoryReview,
+    /// Why the entry was rejected
+    #[serde(default)]
+    pub review_reason: Option<String>,
+    /// Times a neuron's output cited the entry as one it relied on
+    #[serde(default)]
+    pub citations: u32,
+    /// Summary the entry was merged into. An archived entry is found by
+    /// its ID only; searches find the summary instead.
+    #[serde(default)]
+    pub archived_into: Option<Uuid>,
+    /// Entries a summary was written from
+    #[serde(default)]
+    pub summary_of: Vec<Uuid>,
+}
+
+fn default_namespace() -> String {
+    MemoryNamespace::Global.to_string()
+}
+
+/// Review state of an entry. Writes to moderated namespaces start out
+/// pending; every other entry is approved.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MemoryReview {
+    #[default]
+    Approved,
+    Pending,
+    Rejected,
+}
+
+impl MemoryReview {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MemoryReview::Approved => "approved",
+            MemoryReview::Pending => "pending",
+            MemoryReview::Rejected => "rejected",
+        }
+    }
+}
+
+impl std::str::FromStr for MemoryReview {
+    type Err = crate::Error;
+
+    fn from_str(s: &str) -> crate::Result<Self> {
+        match s {
+            "approved" => Ok(MemoryReview::Approved),
+            "pending" => Ok(MemoryReview::Pending),
+            "rejected" => Ok(MemoryReview::Rejected),
+            other => Err(crate::Error::Serialization(format!("Unknown memory review state '{}'", other))),
+        }
+    }
 }
 
 /// Type of memory entry
@@ -47,6 +116,7 @@ pub enum MemoryType {
 /// Search parameters for memory retrieval
 #[derive(Debug, Clone)]
 pub struct MemorySearch {
+    pub namespace: Option<String>,
     pub neuron_id: Option<String>,
     pub layer: Option<String>,
     pub memory_type: Option<MemoryType>,
@@ -56,11 +126,15 @@ pub struct MemorySearch {
     pub min_importance: Option<f32>,
     pub limit: usize,
     pub use_semantic_search: bool,
+    /// Review state of the entries to find; approved unless listing the
+    /// review queue
+    pub review: MemoryReview,
 }
 
 impl Default for MemorySearch {
     fn default() -> Self {
         Self {
+            namespace: None,
             neuron_id: None,
             layer: None,
             memory_type: None,
@@ -70,6 +144,7 @@ impl Default for MemorySearch {
             min_importance: None,
             limit: 10,
             use_semantic_search: false,
+            review: MemoryReview::Approved,
         }
     }
 }
@@ -95,15 +170,40 @@ pub trait MemoryStore: Send + Sync {
     /// Retrieve a memory entry by ID
     async fn get(&self, id: Uuid) -> crate::Result<Option<MemoryEntry>>;
     
+    /// Store an entry, replacing any entry with the same ID
+    async fn replace(&self, entry: MemoryEntry) -> crate::Result<()>;
+    
+    /// Every unexpired approved entry, optionally of one namespace and neuron,
+    /// oldest first
+    async fn export(&self, namespace: Option<&str>, neuron_id: Option<&str>) -> crate::Result<Vec<MemoryEntry>>;
+    
     /// Search for memory entries
     async fn search(&self, params: MemorySearch) -> crate::Result<Vec<MemoryEntry>>;
     
+    /// Approve or reject a pending entry, returning whether one was found
+    async fn set_review(&self, id: Uuid, review: MemoryReview, reason: Option<&str>) -> crate::Result<bool>;
+    
     /// Update access count and timestamp
     async fn record_access(&self, id: Uuid) -> crate::Result<()>;
     
-    /// Delete old or unimportant memories
+    /// Count a citation of the entry by a neuron's output
+    async fn record_citation(&self, id: Uuid) -> crate::Result<()>;
+    
+    /// Delete old or unimportant memories; entries an output has cited are
+    /// kept
     async fn cleanup(&self, before: DateTime<Utc>, min_importance: f32) -> crate::Result<u64>;
     
+    /// Delete entries that expired at or before `now`
+    async fn delete_expired(&self, now: DateTime<Utc>) -> crate::Result<u64>;
+    
+    /// Reseal up to `limit` entries whose content is sealed under a retired
+    /// key, returning how many were resealed
+    async fn reseal(&self, limit: usize) -> crate::Result<u64>;
+    
+    /// Entry count, content size and review counts of one namespace, or of
+    /// every namespace
+    async fn namespace_usage(&self, namespace: Option<&str>) -> crate::Result<Vec<NamespaceUsage>>;
+    
     /// Get memory statistics
     async fn get_stats(&self, neuron_id: &str) -> crate::Result<MemoryStats>;
     
@@ -126,8 +226,24 @@ pub struct MemoryStats {
     pub newest_entry: Option<DateTime<Utc>>,
 }
 
+/// Stored size of a namespace
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NamespaceUsage {
+    pub namespace: String,
+    /// Approved and pending entries not archived, which count against the
+    /// quota
+    pub entries: u64,
+    pub bytes: u64,
+    pub pending: u64,
+    pub rejected: u64,
+    /// Entries merged into a summary, which count against the quota no
+    /// more
+    pub archived: u64,
+}
+
 /// Memory builder for creating entries
 pub struct MemoryBuilder {
+    namespace: String,
     neuron_id: String,
     layer: String,
     entry_type: MemoryType,
@@ -139,6 +255,7 @@ pub struct MemoryBuilder {
 impl MemoryBuilder {
     pub fn new(neuron_id: String, layer: String) -> Self {
         Self {
+            namespace: MemoryNamespace::Private(neuron_id.clone()).to_string(),
             neuron_id,
             layer,
             entry_type: MemoryType::Task,
@@ -148,6 +265,11 @@ impl MemoryBuilder {
         }
     }
     
+    pub fn with_namespace(mut self, namespace: &MemoryNamespace) -> Self {
+        self.namespace = namespace.to_string();
+        self
+    }
+    
     pub fn with_type(mut self, entry_type: MemoryType) -> Self {
         self.entry_type = entry_type;
         self
@@ -182,6 +304,13 @@ impl MemoryBuilder {
             importance: self.importance,
             access_count: 0,
             last_accessed: now,
+            namespace: self.namespace,
+            expires_at: None,
+            review: MemoryReview::Approved,
+            review_reason: None,
+            citations: 0,
+            archived_into: None,
+            summary_of: Vec::new(),
         }
     }
 }
\ No newline at end of file
@@ -0,0 +1,683 @@
+//! Memory namespaces, TTLs, quotas and access policies
+//!
+//! Entries live in one of three kinds of namespace: a neuron's private
+//! namespace (`private:<neuron>`), a layer namespace shared by the neurons of
+//! that layer (`layer:<layer>`), and the `global` namespace. [`NamespacedMemory`]
+//! wraps a [`MemoryStore`] and enforces the configured policy, quotas and TTLs
+//! on every read and write made on behalf of a neuron.
+//!
+//! Writes by neurons to a moderated namespace are stored pending and stay
+//! out of reads, and so out of prompts, until approved. They are approved or
+//! rejected through the API, or by a [`MemoryReviewer`] checking them against
+//! the namespace's approved entries in the background.
+//!
+//! A namespace listed in `backends` keeps its entries in a [`MemoryBackend`],
+//! such as a plugin, instead of the memory store.
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+use tracing::{debug, error, info, warn};
+use uuid::Uuid;
+
+use super::{MemoryEntry, MemoryReview, MemorySearch, MemoryStore};
+use crate::config::{MemoryNamespacesConfig, MemoryPolicyConfig, NamespaceLimits};
+use crate::{Error, Result};
+
+/// A memory namespace
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum MemoryNamespace {
+    /// Private to one neuron
+    Private(String),
+    /// Shared by the neurons of a layer
+    Layer(String),
+    /// Shared by every neuron
+    Global,
+}
+
+impl fmt::Display for MemoryNamespace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MemoryNamespace::Private(neuron_id) => write!(f, "private:{}", neuron_id),
+            MemoryNamespace::Layer(layer) => write!(f, "layer:{}", layer),
+            MemoryNamespace::Global => write!(f, "global"),
+        }
+    }
+}
+
+impl FromStr for MemoryNamespace {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.split_once(':') {
+            Some(("private", neuron_id)) if !neuron_id.is_empty() => {
+                Ok(MemoryNamespace::Private(neuron_id.to_string()))
+            }
+            Some(("layer", layer)) if !layer.is_empty() => Ok(MemoryNamespace::Layer(layer.to_string())),
+            None if s == "global" => Ok(MemoryNamespace::Global),
+            _ => Err(Error::InvalidInput(format!(
+                "Invalid memory namespace '{}': expected private:<neuron>, layer:<layer> or global",
+                s
+            ))),
+        }
+    }
+}
+
+/// Kind of access to a namespace
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MemoryAccess {
+    Read,
+    Write,
+}
+
+impl fmt::Display for MemoryAccess {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MemoryAccess::Read => write!(f, "read"),
+            MemoryAccess::Write => write!(f, "write"),
+        }
+    }
+}
+
+/// Decides which neurons may read or write which namespaces
+#[derive(Debug, Clone)]
+pub struct MemoryPolicy {
+    config: MemoryPolicyConfig,
+}
+
+impl MemoryPolicy {
+    pub fn new(config: MemoryPolicyConfig) -> Self {
+        Self { config }
+    }
+
+    /// Whether `neuron_id` on `layer` has `access` to `namespace`
+    pub fn allows(&self, neuron_id: &str, layer: &str, namespace: &MemoryNamespace, access: MemoryAccess) -> bool {
+        let by_default = match namespace {
+            MemoryNamespace::Private(owner) => owner == neuron_id,
+            MemoryNamespace::Layer(ns_layer) if ns_layer == layer => match access {
+                MemoryAccess::Read => self.config.layer_read,
+                MemoryAccess::Write => self.config.layer_write,
+            },
+            MemoryNamespace::Layer(_) => false,
+            MemoryNamespace::Global => match access {
+                MemoryAccess::Read => self.config.global_read,
+                MemoryAccess::Write => self.config.global_write,
+            },
+        };
+        if by_default {
+            return true;
+        }
+
+        let namespace = namespace.to_string();
+        let layer_principal = format!("layer:{}", layer);
+        self.config.grants.iter()
+            .filter(|grant| grant.namespace == namespace)
+            .filter(|grant| grant.principal == "*" || grant.principal == neuron_id || grant.principal == layer_principal)
+            .any(|grant| match access {
+                MemoryAccess::Read => grant.read || grant.write,
+                MemoryAccess::Write => grant.write,
+            })
+    }
+
+    /// Like [`allows`](Self::allows), but returns a descriptive error
+    pub fn check(&self, neuron_id: &str, layer: &str, namespace: &MemoryNamespace, access: MemoryAccess) -> Result<()> {
+        if self.allows(neuron_id, layer, namespace, access) {
+            Ok(())
+        } else {
+            Err(Error::PermissionDenied(format!(
+                "neuron {} ({}) may not {} memory namespace {}",
+                neuron_id, layer, access, namespace
+            )))
+        }
+    }
+}
+
+/// Usage of a namespace against its limits
+#[derive(Debug, Clone, Serialize)]
+pub struct NamespaceStats {
+    pub namespace: String,
+    /// Approved and pending entries
+    pub entries: u64,
+    pub bytes: u64,
+    pub max_entries: Option<u64>,
+    pub max_bytes: Option<u64>,
+    pub ttl_secs: Option<u64>,
+    pub moderated: bool,
+    pub approved: u64,
+    pub pending: u64,
+    pub rejected: u64,
+    /// Entries merged into a summary, not counted in `entries` or `bytes`
+    pub archived: u64,
+}
+
+/// Outcome of reviewing a pending write
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReviewVerdict {
+    Approve,
+    Reject(String),
+    /// Left pending, e.g. when the reviewer could not decide
+    Defer,
+}
+
+/// Reviews pending writes to moderated namespaces
+#[async_trait]
+pub trait MemoryReviewer: Send + Sync {
+    /// Verdict on `entry`, given the newest approved entries of its namespace
+    async fn review(&self, entry: &MemoryEntry, approved: &[MemoryEntry]) -> Result<ReviewVerdict>;
+}
+
+/// Outcome of one automatic review pass
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ReviewSummary {
+    pub approved: u64,
+    pub rejected: u64,
+    pub deferred: u64,
+}
+
+/// Stores the entries of one namespace in place of the memory store
+#[async_trait]
+pub trait MemoryBackend: Send + Sync {
+    async fn get(&self, id: Uuid) -> Result<Option<MemoryEntry>>;
+
+    /// Store an entry, replacing any entry with the same ID
+    async fn set(&self, entry: MemoryEntry) -> Result<()>;
+
+    /// Delete an entry, returning whether there was one with `id`
+    async fn delete(&self, id: Uuid) -> Result<bool>;
+
+    /// Entries whose stored form contains `query`, every entry if it is
+    /// empty, most recently used first; at most `limit` unless it is 0
+    async fn search(&self, query: &str, limit: usize) -> Result<Vec<MemoryEntry>>;
+}
+
+/// Pending writes reviewed per pass
+const REVIEW_BATCH: usize = 100;
+
+/// Policy- and quota-enforcing view of a memory store
+pub struct NamespacedMemory {
+    store: Arc<dyn MemoryStore>,
+    config: MemoryNamespacesConfig,
+    policy: MemoryPolicy,
+    reviewer: Option<Arc<dyn MemoryReviewer>>,
+    /// Backends of the namespaces listed in `config.backends`
+    backends: HashMap<String, Arc<dyn MemoryBackend>>,
+}
+
+impl NamespacedMemory {
+    pub fn new(store: Arc<dyn MemoryStore>, config: MemoryNamespacesConfig) -> Self {
+        let policy = MemoryPolicy::new(config.policy.clone());
+        Self { store, config, policy, reviewer: None, backends: HashMap::new() }
+    }
+
+    /// Review pending writes automatically with `reviewer`
+    pub fn set_reviewer(&mut self, reviewer: Arc<dyn MemoryReviewer>) {
+        self.reviewer = Some(reviewer);
+    }
+
+    /// Keep the entries of `namespace` in `backend`
+    pub fn set_backend(&mut self, namespace: &MemoryNamespace, backend: Arc<dyn MemoryBackend>) {
+        self.backends.insert(namespace.to_string(), backend);
+    }
+
+    /// Backend of a namespace listed in `backends`. Until its backend is
+    /// set, the namespace fails reads and refuses writes.
+    fn backend_for(&self, namespace: &MemoryNamespace) -> Option<Result<&Arc<dyn MemoryBackend>>> {
+        let name = namespace.to_string();
+        let config = self.config.backends.get(&name)?;
+        Some(self.backends.get(&name).ok_or_else(|| {
+            Error::ReadOnly(format!(
+                "memory namespace {} is stored by plugin {}, which is not loaded",
+                namespace, config.plugin
+            ))
+        }))
+    }
+
+    /// Underlying store, bypassing namespace enforcement
+    pub fn store(&self) -> Arc<dyn MemoryStore> {
+        self.store.clone()
+    }
+
+    /// Access policy
+    pub fn policy(&self) -> &MemoryPolicy {
+        &self.policy
+    }
+
+    /// Limits that apply to a namespace
+    pub fn limits_for(&self, namespace: &MemoryNamespace) -> &NamespaceLimits {
+        if let Some(limits) = self.config.overrides.get(&namespace.to_string()) {
+            return limits;
+        }
+        match namespace {
+            MemoryNamespace::Private(_) => &self.config.private,
+            MemoryNamespace::Layer(_) => &self.config.layer,
+            MemoryNamespace::Global => &self.config.global,
+        }
+    }
+
+    /// Write an entry to `namespace` on behalf of a neuron. In a moderated
+    /// namespace the entry is stored pending.
+    pub async fn write(&self, neuron_id: &str, layer: &str, namespace: &MemoryNamespace, mut entry: MemoryEntry) -> Result<Uuid> {
+        self.policy.check(neuron_id, layer, namespace, MemoryAccess::Write)?;
+
+        let limits = self.limits_for(namespace);
+        if let Some(backend) = self.backend_for(namespace) {
+            entry.namespace = namespace.to_string();
+            entry.expires_at = limits.ttl_secs
+                .map(|ttl| entry.timestamp + chrono::Duration::seconds(ttl as i64));
+            entry.review = MemoryReview::Approved;
+            let id = entry.id;
+            backend?.set(entry).await?;
+            return Ok(id);
+        }
+        if limits.max_entries.is_some() || limits.max_bytes.is_some() {
+            let name = namespace.to_string();
+            let usage = self.store.namespace_usage(Some(&name)).await?
+                .into_iter()
+                .next();
+            let (entries, bytes) = usage.map(|u| (u.entries, u.bytes)).unwrap_or((0, 0));
+
+            if let Some(max) = limits.max_entries {
+                if entries + 1 > max {
+                    return Err(Error::QuotaExceeded(format!(
+                        "memory namespace {} is full ({} of {} entries)",
+                        namespace, entries, max
+                    )));
+                }
+            }
+            if let Some(max) = limits.max_bytes {
+                let size = entry.content.len() as u64;
+                if bytes + size > max {
+                    return Err(Error::QuotaExceeded(format!(
+                        "memory namespace {} is full ({} + {} of {} bytes)",
+                        namespace, bytes, size, max
+                    )));
+                }
+            }
+        }
+
+        entry.namespace = namespace.to_string();
+        entry.expires_at = limits.ttl_secs
+            .map(|ttl| entry.timestamp + chrono::Duration::seconds(ttl as i64));
+        if limits.moderated {
+            entry.review = MemoryReview::Pending;
+            debug!("Memory write {} to {} is pending review", entry.id, namespace);
+        }
+
+        self.store.store(entry).await
+    }
+
+    /// Search `namespace` on behalf of a neuron
+    pub async fn read(&self, neuron_id: &str, layer: &str, namespace: &MemoryNamespace, mut search: MemorySearch) -> Result<Vec<MemoryEntry>> {
+        self.policy.check(neuron_id, layer, namespace, MemoryAccess::Read)?;
+        if let Some(backend) = self.backend_for(namespace) {
+            let backend = backend?;
+            let query = search.content_query.clone().unwrap_or_default();
+            // The backend's limit only holds when every entry it finds is kept
+            let filtered = !query.is_empty()
+                || search.neuron_id.is_some()
+                || search.layer.is_some()
+                || search.memory_type.is_some()
+                || search.start_time.is_some()
+                || search.end_time.is_some()
+                || search.min_importance.is_some()
+                || self.limits_for(namespace).ttl_secs.is_some();
+            let limit = if filtered { 0 } else { search.limit };
+            let now = Utc::now();
+            return Ok(backend.search(&query, limit).await?
+                .into_iter()
+                .filter(|entry| matches_search(entry, &search, now))
+                .take(search.limit)
+                .collect());
+        }
+        search.namespace = Some(namespace.to_string());
+        self.store.search(search).await
+    }
+
+    /// Pending writes, optionally of one namespace, newest first
+    pub async fn pending(&self, namespace: Option<&MemoryNamespace>, limit: usize) -> Result<Vec<MemoryEntry>> {
+        self.store.search(MemorySearch {
+            namespace: namespace.map(|ns| ns.to_string()),
+            review: MemoryReview::Pending,
+            limit,
+            ..Default::default()
+        }).await
+    }
+
+    /// Approve a pending write, returning whether there was one with `id`
+    pub async fn approve(&self, id: Uuid) -> Result<bool> {
+        let approved = self.store.set_review(id, MemoryReview::Approved, None).await?;
+        if approved {
+            info!("Memory write {} approved", id);
+        }
+        Ok(approved)
+    }
+
+    /// Reject a pending write, keeping it with `reason`; returns whether
+    /// there was one with `id`
+    pub async fn reject(&self, id: Uuid, reason: &str) -> Result<bool> {
+        let rejected = self.store.set_review(id, MemoryReview::Rejected, Some(reason)).await?;
+        if rejected {
+            info!("Memory write {} rejected: {}", id, reason);
+        }
+        Ok(rejected)
+    }
+
+    /// Run the reviewer over the newest pending writes. Does nothing without
+    /// a reviewer.
+    pub async fn review_pending(&self) -> Result<ReviewSummary> {
+        let mut summary = ReviewSummary::default();
+        let Some(reviewer) = &self.reviewer else {
+            return Ok(summary);
+        };
+        for entry in self.pending(None, REVIEW_BATCH).await? {
+            let approved = self.store.search(MemorySearch {
+                namespace: Some(entry.namespace.clone()),
+                limit: self.config.moderation.context_entries,
+                ..Default::default()
+            }).await?;
+            let verdict = match reviewer.review(&entry, &approved).await {
+                Ok(verdict) => verdict,
+                Err(e) => {
+                    warn!("Review of memory write {} failed, leaving it pending: {}", entry.id, e);
+                    ReviewVerdict::Defer
+                }
+            };
+            match verdict {
+                ReviewVerdict::Approve => {
+                    self.approve(entry.id).await?;
+                    summary.approved += 1;
+                }
+                ReviewVerdict::Reject(reason) => {
+                    self.reject(entry.id, &reason).await?;
+                    summary.rejected += 1;
+                }
+                ReviewVerdict::Defer => summary.deferred += 1,
+            }
+        }
+        Ok(summary)
+    }
+
+    /// Delete expired entries
+    pub async fn sweep(&self) -> Result<u64> {
+        let now = Utc::now();
+        let mut deleted = self.store.delete_expired(now).await?;
+        for (namespace, backend) in &self.backends {
+            match sweep_backend(backend.as_ref(), now).await {
+                Ok(swept) => deleted += swept,
+                Err(e) => warn!("Expiry sweep of memory namespace {} failed: {}", namespace, e),
+            }
+        }
+        if deleted > 0 {
+            debug!("Memory sweep removed {} expired entries", deleted);
+        }
+        Ok(deleted)
+    }
+
+    /// Usage of every non-empty namespace against its limits
+    pub async fn stats(&self) -> Result<Vec<NamespaceStats>> {
+        let usage = self.store.namespace_usage(None).await?;
+        Ok(usage.into_iter()
+            .map(|usage| {
+                let limits = usage.namespace.parse::<MemoryNamespace>()
+                    .map(|ns| self.limits_for(&ns).clone())
+                    .unwrap_or_default();
+                NamespaceStats {
+                    namespace: usage.namespace,
+                    entries: usage.entries,
+                    bytes: usage.bytes,
+                    max_entries: limits.max_entries,
+                    max_bytes: limits.max_bytes,
+                    ttl_secs: limits.ttl_secs,
+                    moderated: limits.moderated,
+                    approved: usage.entries - usage.pending,
+                    pending: usage.pending,
+                    rejected: usage.rejected,
+                    archived: usage.archived,
+                }
+            })
+            .collect())
+    }
+}
+
+/// Whether a backend's entry is one `search` asks for
+fn matches_search(entry: &MemoryEntry, search: &MemorySearch, now: DateTime<Utc>) -> bool {
+    entry.review == search.review
+        && entry.expires_at.is_none_or(|expires_at| expires_at > now)
+        && search.content_query.as_ref().is_none_or(|query| entry.content.contains(query.as_str()))
+        && search.neuron_id.as_ref().is_none_or(|neuron_id| &entry.neuron_id == neuron_id)
+        && search.layer.as_ref().is_none_or(|layer| &entry.layer == layer)
+        && search.memory_type.as_ref().is_none_or(|memory_type| &entry.entry_type == memory_type)
+        && search.start_time.is_none_or(|start| entry.timestamp >= start)
+        && search.end_time.is_none_or(|end| entry.timestamp <= end)
+        && search.min_importance.is_none_or(|min| entry.importance >= min)
+}
+
+/// Delete a backend's entries that expired at or before `now`
+async fn sweep_backend(backend: &dyn MemoryBackend, now: DateTime<Utc>) -> Result<u64> {
+    let mut deleted = 0;
+    for entry in backend.search("", 0).await? {
+        if entry.expires_at.is_some_and(|expires_at| expires_at <= now) && backend.delete(entry.id).await? {
+            deleted += 1;
+        }
+    }
+    Ok(deleted)
+}
+
+/// Background task reviewing pending writes at the configured interval
+pub async fn review_task(memory: Arc<NamespacedMemory>) {
+    let period = std::time::Duration::from_secs(memory.config.moderation.review_interval_secs.max(1));
+    let mut interval = tokio::time::interval(period);
+
+    loop {
+        interval.tick().await;
+        match memory.review_pending().await {
+            Ok(summary) if summary.approved + summary.rejected > 0 => debug!(
+                "Memory review approved {} and rejected {} writes",
+                summary.approved, summary.rejected
+            ),
+            Ok(_) => {}
+            Err(e) => error!("Memory review failed: {}", e),
+        }
+    }
+}
+
+/// Background task sweeping expired entries at the configured interval
+pub async fn sweep_task(memory: Arc<NamespacedMemory>) {
+    let period = std::time::Duration::from_secs(memory.config.sweep_interval_secs.max(1));
+    let mut interval = tokio::time::interval(period);
+
+    loop {
+        interval.tick().await;
+        if let Err(e) = memory.sweep().await {
+            error!("Memory expiry sweep failed: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::MemoryGrant;
+    use crate::memory::{MemoryBuilder, SqliteMemoryStore};
+
+    async fn memory(config: MemoryNamespacesConfig) -> NamespacedMemory {
+        let store = SqliteMemoryStore::in_memory().await.unwrap();
+        store.initialize().await.unwrap();
+        NamespacedMemory::new(Arc::new(store), config)
+    }
+
+    fn entry(neuron_id: &str, content: &str) -> MemoryEntry {
+        MemoryBuilder::new(neuron_id.to_string(), "L3".to_string())
+            .with_content(content.to_string())
+            .build()
+    }
+
+    #[test]
+    fn test_namespace_round_trip() {
+        for name in ["private:n1", "layer:L3", "global"] {
+            assert_eq!(name.parse::<MemoryNamespace>().unwrap().to_string(), name);
+        }
+        assert!("private:".parse::<MemoryNamespace>().is_err());
+        assert!("team:x".parse::<MemoryNamespace>().is_err());
+    }
+
+    #[test]
+    fn test_policy_matrix() {
+        let mut config = MemoryPolicyConfig::default();
+        config.grants.push(MemoryGrant {
+            namespace: "layer:L2".to_string(),
+            principal: "layer:L3".to_string(),
+            read: true,
+            write: false,
+        });
+        let policy = MemoryPolicy::new(config);
+        let own = MemoryNamespace::Private("n1".to_string());
+        let other = MemoryNamespace::Private("n2".to_string());
+        let layer = MemoryNamespace::Layer("L3".to_string());
+        let lower = MemoryNamespace::Layer("L2".to_string());
+        let upper = MemoryNamespace::Layer("L4".to_string());
+        use MemoryAccess::{Read, Write};
+
+        // (namespace, access, allowed) for neuron n1 on L3
+        let matrix = [
+            (&own, Read, true),
+            (&own, Write, true),
+            (&other, Read, false),
+            (&other, Write, false),
+            (&layer, Read, true),
+            (&layer, Write, false),
+            (&lower, Read, true),
+            (&lower, Write, false),
+            (&upper, Read, false),
+            (&upper, Write, false),
+            (&MemoryNamespace::Global, Read, true),
+            (&MemoryNamespace::Global, Write, false),
+        ];
+        for (namespace, access, allowed) in matrix {
+            assert_eq!(
+                policy.allows("n1", "L3", namespace, access),
+                allowed,
+                "{} {}",
+                access,
+                namespace
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_policy_violation_is_reported() {
+        let memory = memory(MemoryNamespacesConfig::default()).await;
+        let other = MemoryNamespace::Private("n2".to_string());
+
+        let err = memory.write("n1", "L3", &other, entry("n1", "x")).await.unwrap_err();
+        assert!(matches!(err, Error::PermissionDenied(_)));
+        assert!(err.to_string().contains("private:n2"));
+    }
+
+    #[tokio::test]
+    async fn test_quota_rejects_writes_over_limit() {
+        let config = MemoryNamespacesConfig {
+            private: NamespaceLimits { ttl_secs: None, max_entries: Some(2), max_bytes: None, moderated: false },
+            ..MemoryNamespacesConfig::default()
+        };
+        let memory = memory(config).await;
+        let own = MemoryNamespace::Private("n1".to_string());
+
+        memory.write("n1", "L3", &own, entry("n1", "a")).await.unwrap();
+        memory.write("n1", "L3", &own, entry("n1", "b")).await.unwrap();
+        let err = memory.write("n1", "L3", &own, entry("n1", "c")).await.unwrap_err();
+        assert!(matches!(err, Error::QuotaExceeded(_)));
+
+        // Quotas are per namespace instance
+        let other = MemoryNamespace::Private("n2".to_string());
+        memory.write("n2", "L3", &other, entry("n2", "a")).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_entries_expire_after_ttl() {
+        let config = MemoryNamespacesConfig {
+            private: NamespaceLimits { ttl_secs: Some(1), max_entries: None, max_bytes: None, moderated: false },
+            ..MemoryNamespacesConfig::default()
+        };
+        let memory = memory(config).await;
+        let own = MemoryNamespace::Private("n1".to_string());
+
+        memory.write("n1", "L3", &own, entry("n1", "short lived")).await.unwrap();
+        let found = memory.read("n1", "L3", &own, MemorySearch::default()).await.unwrap();
+        assert_eq!(found.len(), 1);
+        assert!(found[0].expires_at.is_some());
+
+        tokio::time::sleep(std::time::Duration::from_millis(2100)).await;
+
+        // Hidden from reads immediately, removed by the sweep
+        assert!(memory.read("n1", "L3", &own, MemorySearch::default()).await.unwrap().is_empty());
+        assert_eq!(memory.sweep().await.unwrap(), 1);
+        assert!(memory.stats().await.unwrap().is_empty());
+    }
+
+    fn moderated_config() -> MemoryNamespacesConfig {
+        let mut config = MemoryNamespacesConfig::default();
+        config.private.moderated = true;
+        config
+    }
+
+    #[tokio::test]
+    async fn test_moderated_write_is_hidden_until_approved() {
+        let memory = memory(moderated_config()).await;
+        let own = MemoryNamespace::Private("n1".to_string());
+
+        let id = memory.write("n1", "L3", &own, entry("n1", "lesson")).await.unwrap();
+        assert!(memory.read("n1", "L3", &own, MemorySearch::default()).await.unwrap().is_empty());
+        let context = memory.store().build_context("n1", "lesson").await.unwrap();
+        assert!(context.recent_tasks.is_empty() && context.similar_experiences.is_empty());
+        assert_eq!(memory.pending(Some(&own), 10).await.unwrap()[0].id, id);
+
+        assert!(memory.approve(id).await.unwrap());
+        assert!(!memory.approve(id).await.unwrap(), "only pending writes are approved");
+        let found = memory.read("n1", "L3", &own, MemorySearch::default()).await.unwrap();
+        assert_eq!(found[0].id, id);
+        assert_eq!(memory.store().build_context("n1", "lesson").await.unwrap().recent_tasks.len(), 1);
+    }
+
+    /// Rejects entries contradicting an approved one
+    struct ContradictionReviewer;
+
+    #[async_trait]
+    impl MemoryReviewer for ContradictionReviewer {
+        async fn review(&self, entry: &MemoryEntry, approved: &[MemoryEntry]) -> Result<ReviewVerdict> {
+            Ok(match approved.iter().find(|e| entry.content == format!("not {}", e.content)) {
+                Some(existing) => ReviewVerdict::Reject(format!("contradicts '{}'", existing.content)),
+                None if entry.content.is_empty() => ReviewVerdict::Defer,
+                None => ReviewVerdict::Approve,
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reviewer_approves_and_rejects_pending_writes() {
+        let mut memory = memory(moderated_config()).await;
+        memory.set_reviewer(Arc::new(ContradictionReviewer));
+        let own = MemoryNamespace::Private("n1".to_string());
+
+        memory.write("n1", "L3", &own, entry("n1", "retries help")).await.unwrap();
+        let summary = memory.review_pending().await.unwrap();
+        assert_eq!(summary, ReviewSummary { approved: 1, rejected: 0, deferred: 0 });
+
+        let contradiction = memory.write("n1", "L3", &own, entry("n1", "not retries help")).await.unwrap();
+        memory.write("n1", "L3", &own, entry("n1", "")).await.unwrap();
+        let summary = memory.review_pending().await.unwrap();
+        assert_eq!(summary, ReviewSummary { approved: 0, rejected: 1, deferred: 1 });
+
+        let rejected = memory.store().get(contradiction).await.unwrap().unwrap();
+        assert_eq!(rejected.review, MemoryReview::Rejected);
+        assert_eq!(rejected.review_reason.as_deref(), Some("contradicts 'retries help'"));
+
+        let stats = memory.stats().await.unwrap();
+        assert_eq!((stats[0].approved, stats[0].pending, stats[0].rejected), (1, 1, 1));
+        assert!(stats[0].moderated);
+    }
+}
@@ -1,15 +1,39 @@
 //! SQLite implementation of memory storage
+//!
+//! With a [`TenantKeyring`] the content of entries whose metadata names an
+//! organization (`auth.org_id`) with encryption enabled is sealed before it
+//! is written and unsealed when read. Sealed content cannot be matched by
+//! substring, so content searches find sealed entries through their
+//! embeddings instead, or not at all, as [`SealedSearch`] says.
+//!
+//! With a [`ColdTier`] the content of entries moved to it is fetched from
+//! its object store as they are read, see [`super::tiering`].
+//!
+//! Searches never return an entry archived into a summary, see
+//! [`super::summarization`]; an archived entry that matches a search
+//! brings its summary into the results instead.
 
 use async_trait::async_trait;
-use sqlx::{SqlitePool, sqlite::SqlitePoolOptions, FromRow};
+use sqlx::FromRow;
 use chrono::{DateTime, Utc};
 use uuid::Uuid;
 use std::path::Path;
+use std::sync::Arc;
 use tracing::{debug, info, warn};
 
-use super::{MemoryStore, MemoryEntry, MemorySearch, MemoryStats, MemoryContext, MemoryType};
+use super::{
+    EmbeddingGenerator, MemoryStore, MemoryEntry, MemoryReview, MemorySearch, MemoryStats, MemoryContext, MemoryType,
+    NamespaceUsage,
+};
+use super::tiering::{self, ColdTier, TierSize, TierUsage};
+use crate::encryption::{self, TenantKeyring};
+use crate::metadata_schema::keys;
+use crate::sqlite::{SqlitePools, SqliteTuning};
 use crate::{Result, Error};
 
+/// Sealed entries a content search compares embeddings with at most
+const SEALED_SEARCH_SCAN: i64 = 1000;
+
 /// Row type for memory queries
 #[derive(FromRow)]
 struct MemoryRow {
@@ -24,16 +48,177 @@ struct MemoryRow {
     importance: f32,
     access_count: i64,
     last_accessed: i64,
+    namespace: String,
+    expires_at: Option<i64>,
+    review: String,
+    review_reason: Option<String>,
+    citations: i64,
+    /// Object holding the content of a cold entry, whose row is a stub
+    cold_key: Option<String>,
+    archived_into: Option<String>,
+    /// IDs of the entries a summary was written from, as a JSON array
+    summary_of: Option<String>,
+}
+
+impl MemoryRow {
+    fn into_entry(self) -> Result<MemoryEntry> {
+        let entry_type: MemoryType = serde_json::from_str(&self.entry_type)
+            .map_err(|e| Error::Serialization(e.to_string()))?;
+        let metadata: serde_json::Value = serde_json::from_str(&self.metadata)
+            .map_err(|e| Error::Serialization(e.to_string()))?;
+        let embedding = self.embedding.as_ref().map(|bytes| {
+            bytes.chunks_exact(4)
+                .map(|chunk| f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+                .collect()
+        });
+        let summary_of = match &self.summary_of {
+            Some(ids) => serde_json::from_str(ids).map_err(|e| Error::Serialization(e.to_string()))?,
+            None => Vec::new(),
+        };
+        
+        Ok(MemoryEntry {
+            id: Uuid::parse_str(&self.id)
+                .map_err(|e| Error::Other(anyhow::anyhow!("Invalid UUID: {}", e)))?,
+            neuron_id: self.neuron_id,
+            layer: self.layer,
+            timestamp: DateTime::from_timestamp(self.timestamp, 0)
+                .unwrap_or_else(Utc::now),
+            entry_type,
+            content: self.content,
+            metadata,
+            embedding,
+            importance: self.importance,
+            access_count: self.access_count as u32,
+            last_accessed: DateTime::from_timestamp(self.last_accessed, 0)
+                .unwrap_or_else(Utc::now),
+            namespace: self.namespace,
+            expires_at: self.expires_at.and_then(|ts| DateTime::from_timestamp(ts, 0)),
+            review: self.review.parse()?,
+            review_reason: self.review_reason,
+            citations: self.citations as u32,
+            archived_into: self.archived_into
+                .map(|id| Uuid::parse_str(&id).map_err(|e| Error::Other(anyhow::anyhow!("Invalid UUID: {}", e))))
+                .transpose()?,
+            summary_of,
+        })
+    }
+}
+
+/// An entry's column values, bound to an INSERT
+struct InsertRow {
+    id: String,
+    neuron_id: String,
+    layer: String,
+    timestamp: i64,
+    entry_type: String,
+    content: String,
+    metadata: String,
+    embedding: Option<Vec<u8>>,
+    importance: f32,
+    access_count: i64,
+    last_accessed: i64,
+    namespace: String,
+    expires_at: Option<i64>,
+    review: &'static str,
+    review_reason: Option<String>,
+    citations: i64,
+    archived_into: Option<String>,
+    summary_of: Option<String>,
+}
+
+impl InsertRow {
+    fn new(entry: &MemoryEntry) -> Result<Self> {
+        Ok(Self {
+            id: entry.id.to_string(),
+            neuron_id: entry.neuron_id.clone(),
+            layer: entry.layer.clone(),
+            timestamp: entry.timestamp.timestamp(),
+            entry_type: serde_json::to_string(&entry.entry_type)
+                .map_err(|e| Error::Serialization(e.to_string()))?,
+            content: entry.content.clone(),
+            metadata: serde_json::to_string(&entry.metadata)
+                .map_err(|e| Error::Serialization(e.to_string()))?,
+            embedding: entry.embedding.as_ref()
+                .map(|e| e.iter().flat_map(|f| f.to_le_bytes()).collect()),
+            importance: entry.importance,
+            access_count: entry.access_count as i64,
+            last_accessed: entry.last_accessed.timestamp(),
+            namespace: entry.namespace.clone(),
+            expires_at: entry.expires_at.map(|t| t.timestamp()),
+            review: entry.review.as_str(),
+            review_reason: entry.review_reason.clone(),
+            citations: entry.citations as i64,
+            archived_into: entry.archived_into.map(|id| id.to_string()),
+            summary_of: (!entry.summary_of.is_empty())
+                .then(|| serde_json::to_string(&entry.summary_of))
+                .transpose()
+                .map_err(|e| Error::Serialization(e.to_string()))?,
+        })
+    }
+    
+    fn query(&self) -> sqlx::query::Query<'_, sqlx::Sqlite, sqlx::sqlite::SqliteArguments<'_>> {
+        sqlx::query(r#"
+            INSERT INTO memories (
+                id, neuron_id, layer, timestamp, entry_type, 
+                content, metadata, embedding, importance, 
+                access_count, last_accessed, namespace, expires_at,
+                review, review_reason, citations, archived_into, summary_of
+            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+        "#)
+        .bind(&self.id)
+        .bind(&self.neuron_id)
+        .bind(&self.layer)
+        .bind(self.timestamp)
+        .bind(&self.entry_type)
+        .bind(&self.content)
+        .bind(&self.metadata)
+        .bind(&self.embedding)
+        .bind(self.importance)
+        .bind(self.access_count)
+        .bind(self.last_accessed)
+        .bind(&self.namespace)
+        .bind(self.expires_at)
+        .bind(self.review)
+        .bind(&self.review_reason)
+        .bind(self.citations)
+        .bind(&self.archived_into)
+        .bind(&self.summary_of)
+    }
+}
+
+/// Columns selected for [`MemoryRow`]
+const MEMORY_COLUMNS: &str = "id, neuron_id, layer, timestamp, entry_type, \
+    content, metadata, embedding, importance, \
+    access_count, last_accessed, namespace, expires_at, \
+    review, review_reason, citations, cold_key, archived_into, summary_of";
+
+/// How content searches treat entries whose content is sealed
+pub enum SealedSearch {
+    /// Sealed entries never match a content query
+    Disabled,
+    /// Sealed entries match when their embedding is at least
+    /// `min_similarity` similar to the query's
+    Embedding {
+        generator: EmbeddingGenerator,
+        min_similarity: f32,
+    },
 }
 
 /// SQLite-based memory store
 pub struct SqliteMemoryStore {
-    pool: SqlitePool,
+    pools: SqlitePools,
+    keyring: Option<Arc<TenantKeyring>>,
+    sealed_search: SealedSearch,
+    cold: Option<Arc<ColdTier>>,
 }
 
 impl SqliteMemoryStore {
     /// Create a new SQLite memory store
     pub async fn new(database_path: &str) -> Result<Self> {
+        if database_path == ":memory:" {
+            return Self::in_memory().await;
+        }
+
         // Create directory if it doesn't exist
         if let Some(parent) = Path::new(database_path).parent() {
             std::fs::create_dir_all(parent)
@@ -46,28 +231,476 @@ impl SqliteMemoryStore {
                 .map_err(Error::Io)?;
         }
         
-        // SQLx connection string for SQLite with mode=rwc to create if not exists
         let connection_string = format!("sqlite:{}?mode=rwc", database_path);
         debug!("Connecting to SQLite with: {}", connection_string);
         
-        let pool = SqlitePoolOptions::new()
-            .max_connections(5)
-            .connect(&connection_string)
-            .await
-            .map_err(|e| Error::Other(anyhow::anyhow!("Failed to connect to SQLite: {}", e)))?;
+        let pools = SqlitePools::connect(&connection_string, SqliteTuning::default()).await?;
+        pools.quick_check(database_path).await?;
             
-        Ok(Self { pool })
+        Ok(Self::with_pools(pools))
+    }
+    
+    /// Create a store on pools opened elsewhere
+    pub fn with_pools(pools: SqlitePools) -> Self {
+        Self { pools, keyring: None, sealed_search: SealedSearch::Disabled, cold: None }
+    }
+    
+    /// Seal the content of entries of organizations `keyring` encrypts
+    pub fn with_keyring(mut self, keyring: Arc<TenantKeyring>, sealed_search: SealedSearch) -> Self {
+        self.keyring = Some(keyring);
+        self.sealed_search = sealed_search;
+        self
+    }
+    
+    /// Read the content of cold entries from `cold`, and move entries
+    /// there with [`SqliteMemoryStore::demote`]
+    pub fn with_cold_tier(mut self, cold: Arc<ColdTier>) -> Self {
+        self.cold = Some(cold);
+        self
+    }
+    
+    /// Cold tier, if one is configured
+    pub fn cold_tier(&self) -> Option<&Arc<ColdTier>> {
+        self.cold.as_ref()
+    }
+    
+    /// Reader and writer pools, for connection statistics
+    pub fn pools(&self) -> &SqlitePools {
+        &self.pools
     }
     
     /// Create a new in-memory SQLite store (for testing)
     pub async fn in_memory() -> Result<Self> {
-        let pool = SqlitePoolOptions::new()
-            .max_connections(1)
-            .connect("sqlite::memory:")
+        let pools = SqlitePools::connect("sqlite::memory:", SqliteTuning::default()).await?;
+        Ok(Self::with_pools(pools))
+    }
+    
+    /// Column values of `entry`, its content sealed if its organization
+    /// is encrypted
+    async fn insert_row(&self, entry: &MemoryEntry) -> Result<InsertRow> {
+        let mut row = InsertRow::new(entry)?;
+        if let Some(keyring) = &self.keyring {
+            let org_id = entry.metadata.get(keys::auth::ORG_ID).and_then(|org| org.as_str());
+            let sealed = keyring.seal(org_id, &row.content).await?;
+            // Sealed content is only found by its embedding
+            if let (true, None, SealedSearch::Embedding { generator, .. }) =
+                (encryption::is_sealed(&sealed), &row.embedding, &self.sealed_search)
+            {
+                let embedding = generator.generate(&row.content).await?;
+                row.embedding = Some(embedding.iter().flat_map(|f| f.to_le_bytes()).collect());
+            }
+            row.content = sealed;
+        }
+        Ok(row)
+    }
+    
+    /// Entries of `rows` with their content fetched from the cold tier and
+    /// unsealed. Content sealed under a retired key is resealed under the
+    /// current one on the way, and entries read often enough while cold are
+    /// promoted.
+    async fn entries(&self, rows: Vec<MemoryRow>) -> Result<Vec<MemoryEntry>> {
+        self.read_entries(rows, true).await
+    }
+    
+    /// [`SqliteMemoryStore::entries`], counting reads of cold entries
+    /// towards their promotion only if `count_cold_reads`
+    async fn read_entries(&self, rows: Vec<MemoryRow>, count_cold_reads: bool) -> Result<Vec<MemoryEntry>> {
+        let mut resealed = Vec::new();
+        let mut cold_reads = Vec::new();
+        let mut entries = Vec::with_capacity(rows.len());
+        for mut row in rows {
+            let cold_key = self.load_cold(&mut row).await?;
+            if let (Some(key), true) = (&cold_key, count_cold_reads) {
+                cold_reads.push((row.id.clone(), key.clone(), row.content.clone()));
+            }
+            if encryption::is_sealed(&row.content) {
+                let keyring = self.keyring.as_ref().ok_or_else(|| {
+                    Error::Config(format!("Memory {} is encrypted but no key store is configured", row.id))
+                })?;
+                // Cold content is resealed once it is back in the hot tier
+                if let (None, Some(content)) = (&cold_key, keyring.reseal(&row.content)?) {
+                    resealed.push((row.id.clone(), row.content.clone(), content));
+                }
+                row.content = keyring.unseal(&row.content)?;
+            }
+            entries.push(row.into_entry()?);
+        }
+        if !resealed.is_empty() {
+            if let Err(e) = self.store_resealed(&resealed).await {
+                warn!("Failed to reseal {} memories: {}", resealed.len(), e);
+            }
+        }
+        if !cold_reads.is_empty() {
+            if let Err(e) = self.record_cold_reads(&cold_reads).await {
+                warn!("Failed to count reads of {} cold memories: {}", cold_reads.len(), e);
+            }
+        }
+        Ok(entries)
+    }
+    
+    /// Fill in the content of a cold row from the cold tier, returning the
+    /// key it was read from, or `None` for a hot row. An object missing
+    /// because the entry was promoted, replaced or moved again since the
+    /// row was read is looked up again through the row as it is now.
+    async fn load_cold(&self, row: &mut MemoryRow) -> Result<Option<String>> {
+        for _ in 0..2 {
+            let Some(key) = row.cold_key.clone() else {
+                return Ok(None);
+            };
+            let cold = self.cold.as_ref().ok_or_else(|| {
+                Error::Config(format!("Memory {} is in the cold tier but none is configured", row.id))
+            })?;
+            if let Some(bytes) = cold.store().get(&key).await? {
+                row.content = String::from_utf8(bytes)
+                    .map_err(|e| Error::Serialization(format!("Cold content of memory {}: {}", row.id, e)))?;
+                cold.record_read();
+                return Ok(Some(key));
+            }
+            let query = format!("SELECT {} FROM memories WHERE id = ?", MEMORY_COLUMNS);
+            let current = sqlx::query_as::<_, MemoryRow>(&query)
+                .bind(&row.id)
+                .fetch_optional(self.pools.writer())
+                .await
+                .map_err(|e| Error::Other(anyhow::anyhow!("Failed to get memory: {}", e)))?;
+            match current {
+                Some(current) if current.cold_key != row.cold_key => *row = current,
+                _ => break,
+            }
+        }
+        Err(Error::Storage(format!("Cold content of memory {} is missing", row.id)))
+    }
+    
+    /// Count a read of each cold entry of `reads`, given as id, object key
+    /// and the content read, promoting those read often enough
+    async fn record_cold_reads(&self, reads: &[(String, String, String)]) -> Result<()> {
+        let Some(cold) = &self.cold else {
+            return Ok(());
+        };
+        let counts = self.pools.write(|pool| async move {
+            let mut tx = pool.begin().await?;
+            let mut counts = Vec::with_capacity(reads.len());
+            for (id, key, _) in reads {
+                let count: Option<i64> = sqlx::query_scalar(
+                    "UPDATE memories SET cold_reads = cold_reads + 1 WHERE id = ? AND cold_key = ? RETURNING cold_reads"
+                )
+                .bind(id)
+                .bind(key)
+                .fetch_optional(&mut *tx)
+                .await?;
+                counts.push(count);
+            }
+            tx.commit().await?;
+            Ok(counts)
+        })
+        .await
+        .map_err(|e| Error::Other(anyhow::anyhow!("Failed to count cold reads: {}", e)))?;
+        
+        for ((id, key, content), count) in reads.iter().zip(counts) {
+            if count.is_some_and(|count| cold.should_promote(count)) {
+                self.promote(cold, id, key, content).await?;
+            }
+        }
+        Ok(())
+    }
+    
+    /// Put `content`, read from the object under `key`, back in the row of
+    /// entry `id` while the row still refers to that object, then delete
+    /// the object
+    async fn promote(&self, cold: &ColdTier, id: &str, key: &str, content: &str) -> Result<bool> {
+        let now = Utc::now().timestamp();
+        let result = self.pools.write(|pool| async move {
+            sqlx::query(
+                "UPDATE memories
+                 SET content = ?, cold_key = NULL, cold_bytes = 0, cold_reads = 0, last_accessed = ?
+                 WHERE id = ? AND cold_key = ?"
+            )
+            .bind(content)
+            .bind(now)
+            .bind(id)
+            .bind(key)
+            .execute(&pool)
             .await
-            .map_err(|e| Error::Other(anyhow::anyhow!("Failed to create in-memory SQLite: {}", e)))?;
-            
-        Ok(Self { pool })
+        })
+        .await
+        .map_err(|e| Error::Other(anyhow::anyhow!("Failed to promote memory: {}", e)))?;
+        if result.rows_affected() == 0 {
+            return Ok(false);
+        }
+        
+        cold.record_promotion();
+        debug!("Promoted memory {} to the hot tier", id);
+        if let Err(e) = cold.store().delete(key).await {
+            warn!("Failed to delete cold content of promoted memory {}, left for reconciliation: {}", id, e);
+        }
+        Ok(true)
+    }
+    
+    /// Hot entries not read since `idle_since`, or less important than
+    /// `min_importance` and never cited, least recently read first. Entries
+    /// awaiting review stay hot.
+    pub async fn tiering_candidates(
+        &self,
+        idle_since: DateTime<Utc>,
+        min_importance: f32,
+        limit: usize,
+    ) -> Result<Vec<Uuid>> {
+        let ids: Vec<String> = sqlx::query_scalar(
+            "SELECT id FROM memories
+             WHERE cold_key IS NULL AND review <> 'pending'
+               AND (last_accessed < ? OR (importance < ? AND citations = 0))
+             ORDER BY last_accessed, id
+             LIMIT ?"
+        )
+        .bind(idle_since.timestamp())
+        .bind(min_importance)
+        .bind(limit as i64)
+        .fetch_all(self.pools.reader())
+        .await
+        .map_err(|e| Error::Other(anyhow::anyhow!("Failed to find memories to move to the cold tier: {}", e)))?;
+        
+        ids.iter()
+            .map(|id| Uuid::parse_str(id).map_err(|e| Error::Other(anyhow::anyhow!("Invalid UUID: {}", e))))
+            .collect()
+    }
+    
+    /// Move the content of hot entry `id` to the cold tier, leaving a stub
+    /// row. Returns false, having moved nothing, when the entry is gone,
+    /// already cold or changed while its content was written.
+    pub async fn demote(&self, id: Uuid) -> Result<bool> {
+        let cold = self.cold.as_ref()
+            .ok_or_else(|| Error::Config("No cold tier is configured".to_string()))?;
+        let id = id.to_string();
+        let _moving = cold.moving.lock().await;
+        
+        let content: Option<String> = sqlx::query_scalar("SELECT content FROM memories WHERE id = ? AND cold_key IS NULL")
+            .bind(&id)
+            .fetch_optional(self.pools.writer())
+            .await
+            .map_err(|e| Error::Other(anyhow::anyhow!("Failed to get memory: {}", e)))?;
+        let Some(content) = content else {
+            return Ok(false);
+        };
+        
+        let key = tiering::object_key(&id);
+        let bytes = content.len() as i64;
+        cold.store().put(&key, content.clone().into_bytes()).await?;
+        
+        // The entry only becomes cold if it is still as it was read
+        let (id, key, content) = (&id, &key, &content);
+        let result = self.pools.write(|pool| async move {
+            sqlx::query(
+                "UPDATE memories SET content = '', cold_key = ?, cold_bytes = ?, cold_reads = 0
+                 WHERE id = ? AND cold_key IS NULL AND content = ?"
+            )
+            .bind(key)
+            .bind(bytes)
+            .bind(id)
+            .bind(content)
+            .execute(&pool)
+            .await
+        })
+        .await;
+        
+        match result {
+            Ok(result) if result.rows_affected() > 0 => Ok(true),
+            result => {
+                if let Err(e) = cold.store().delete(key).await {
+                    warn!("Failed to delete cold content of memory {}, left for reconciliation: {}", id, e);
+                }
+                result
+                    .map(|_| false)
+                    .map_err(|e| Error::Other(anyhow::anyhow!("Failed to move memory to the cold tier: {}", e)))
+            }
+        }
+    }
+    
+    /// Delete cold objects no row refers to, left by an interrupted move, a
+    /// promotion or a deleted or replaced cold entry, returning how many
+    /// were deleted
+    pub async fn reconcile_cold(&self) -> Result<u64> {
+        let Some(cold) = &self.cold else {
+            return Ok(0);
+        };
+        let _moving = cold.moving.lock().await;
+        
+        let objects = cold.store().list(tiering::MEMORY_PREFIX).await?;
+        if objects.is_empty() {
+            return Ok(0);
+        }
+        let referenced: std::collections::HashSet<String> =
+            sqlx::query_scalar("SELECT cold_key FROM memories WHERE cold_key IS NOT NULL")
+                .fetch_all(self.pools.writer())
+                .await
+                .map_err(|e| Error::Other(anyhow::anyhow!("Failed to list cold memories: {}", e)))?
+                .into_iter()
+                .collect();
+        
+        let mut deleted = 0;
+        for key in objects.iter().filter(|key| !referenced.contains(*key)) {
+            cold.store().delete(key).await?;
+            deleted += 1;
+        }
+        if deleted > 0 {
+            info!("Deleted {} cold memory objects no entry refers to", deleted);
+        }
+        Ok(deleted)
+    }
+    
+    /// Entries and content size of the hot and cold tiers
+    pub async fn tier_usage(&self) -> Result<TierUsage> {
+        let (hot_entries, hot_bytes, cold_entries, cold_bytes): (i64, i64, i64, i64) = sqlx::query_as(
+            "SELECT COALESCE(SUM(cold_key IS NULL), 0),
+                    COALESCE(SUM(CASE WHEN cold_key IS NULL THEN LENGTH(CAST(content AS BLOB)) ELSE 0 END), 0),
+                    COALESCE(SUM(cold_key IS NOT NULL), 0),
+                    COALESCE(SUM(cold_bytes), 0)
+             FROM memories"
+        )
+        .fetch_one(self.pools.reader())
+        .await
+        .map_err(|e| Error::Other(anyhow::anyhow!("Failed to get tier usage: {}", e)))?;
+        
+        Ok(TierUsage {
+            hot: TierSize { entries: hot_entries as u64, bytes: hot_bytes as u64 },
+            cold: TierSize { entries: cold_entries as u64, bytes: cold_bytes as u64 },
+        })
+    }
+
+    /// Content size of each organization's entries in both tiers. Entries
+    /// without an organization are not counted.
+    pub async fn org_bytes(&self) -> Result<std::collections::HashMap<String, u64>> {
+        let rows: Vec<(String, i64)> = sqlx::query_as(
+            "SELECT org_id, SUM(bytes) FROM (
+                 SELECT json_extract(metadata, ?) AS org_id,
+                        LENGTH(CAST(content AS BLOB)) + cold_bytes AS bytes
+                 FROM memories
+             )
+             WHERE org_id IS NOT NULL
+             GROUP BY org_id"
+        )
+        .bind(format!("$.\"{}\"", keys::auth::ORG_ID))
+        .fetch_all(self.pools.reader())
+        .await
+        .map_err(|e| Error::Other(anyhow::anyhow!("Failed to get organization memory usage: {}", e)))?;
+
+        Ok(rows.into_iter().map(|(org_id, bytes)| (org_id, bytes as u64)).collect())
+    }
+
+    /// Approved entries of `namespace` that are neither archived nor a
+    /// summary, oldest first
+    pub async fn summarization_candidates(&self, namespace: &str, limit: usize) -> Result<Vec<MemoryEntry>> {
+        let query = format!(
+            "SELECT {} FROM memories
+             WHERE namespace = ? AND review = 'approved'
+               AND archived_into IS NULL AND summary_of IS NULL
+               AND (expires_at IS NULL OR expires_at > ?)
+             ORDER BY timestamp, id
+             LIMIT ?",
+            MEMORY_COLUMNS
+        );
+        let rows = sqlx::query_as::<_, MemoryRow>(&query)
+            .bind(namespace)
+            .bind(Utc::now().timestamp())
+            .bind(limit as i64)
+            .fetch_all(self.pools.reader())
+            .await
+            .map_err(|e| Error::Other(anyhow::anyhow!("Failed to find memories to summarize: {}", e)))?;
+        
+        // Being summarized says nothing about how useful an entry is
+        self.read_entries(rows, false).await
+    }
+    
+    /// Store `summary` and archive the entries of its `summary_of` into it,
+    /// all or nothing. Returns false, having stored nothing, when one of
+    /// them is gone, already archived or no longer approved.
+    pub async fn store_summary(&self, summary: &MemoryEntry) -> Result<bool> {
+        if summary.summary_of.is_empty() {
+            return Err(Error::InvalidInput(format!("Summary {} summarizes no entries", summary.id)));
+        }
+        let row = self.insert_row(summary).await?;
+        let (row, originals) = (&row, &summary.summary_of);
+        self.pools.write(|pool| async move {
+            let mut tx = pool.begin().await?;
+            row.query().execute(&mut *tx).await?;
+            let query = format!(
+                "UPDATE memories SET archived_into = ?
+                 WHERE id IN ({}) AND archived_into IS NULL AND summary_of IS NULL AND review = 'approved'",
+                vec!["?"; originals.len()].join(", ")
+            );
+            let mut archive = sqlx::query(&query).bind(&row.id);
+            for id in originals {
+                archive = archive.bind(id.to_string());
+            }
+            if archive.execute(&mut *tx).await?.rows_affected() != originals.len() as u64 {
+                tx.rollback().await?;
+                return Ok(false);
+            }
+            tx.commit().await?;
+            Ok(true)
+        })
+        .await
+        .map_err(|e| Error::Other(anyhow::anyhow!("Failed to store memory summary: {}", e)))
+    }
+    
+    /// Replace content that has not changed since it was read with its
+    /// resealed form, returning how many entries were updated
+    async fn store_resealed(&self, resealed: &[(String, String, String)]) -> Result<u64> {
+        self.pools.write(|pool| async move {
+            let mut tx = pool.begin().await?;
+            let mut updated = 0;
+            for (id, old, new) in resealed {
+                updated += sqlx::query("UPDATE memories SET content = ? WHERE id = ? AND content = ?")
+                    .bind(new)
+                    .bind(id)
+                    .bind(old)
+                    .execute(&mut *tx)
+                    .await?
+                    .rows_affected();
+            }
+            tx.commit().await?;
+            Ok(updated)
+        })
+        .await
+        .map_err(|e| Error::Other(anyhow::anyhow!("Failed to store resealed memories: {}", e)))
+    }
+    
+    /// Sealed entries matching `filters` whose embedding is similar enough
+    /// to the query's
+    async fn search_sealed(
+        &self,
+        filters: &str,
+        bindings: &[String],
+        content_query: &str,
+    ) -> Result<Vec<MemoryEntry>> {
+        let SealedSearch::Embedding { generator, min_similarity } = &self.sealed_search else {
+            return Ok(Vec::new());
+        };
+        let query_embedding = generator.generate(content_query).await?;
+        
+        let query = format!(
+            "SELECT {} FROM memories WHERE {} AND archived_into IS NULL AND content LIKE 'tenc:%' \
+             AND embedding IS NOT NULL ORDER BY timestamp DESC LIMIT ?",
+            MEMORY_COLUMNS, filters
+        );
+        let mut sql_query = sqlx::query_as::<_, MemoryRow>(&query);
+        for binding in bindings {
+            sql_query = sql_query.bind(binding);
+        }
+        let rows = sql_query
+            .bind(SEALED_SEARCH_SCAN)
+            .fetch_all(self.pools.reader())
+            .await
+            .map_err(|e| Error::Other(anyhow::anyhow!("Failed to search encrypted memories: {}", e)))?;
+        
+        let similar = rows.into_iter()
+            .filter(|row| {
+                let embedding: Vec<f32> = row.embedding.as_deref().unwrap_or_default()
+                    .chunks_exact(4)
+                    .map(|chunk| f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+                    .collect();
+                EmbeddingGenerator::cosine_similarity(&query_embedding, &embedding) >= *min_similarity
+            })
+            .collect();
+        self.entries(similar).await
     }
 }
 
@@ -75,6 +708,7 @@ impl SqliteMemoryStore {
 impl MemoryStore for SqliteMemoryStore {
     async fn initialize(&self) -> Result<()> {
         info!("Initializing SQLite memory store");
+        let pool = self.pools.writer();
         
         // Create memories table
         sqlx::query(r#"
@@ -89,38 +723,146 @@ impl MemoryStore for SqliteMemoryStore {
                 embedding BLOB,
                 importance REAL NOT NULL,
                 access_count INTEGER NOT NULL DEFAULT 0,
-                last_accessed INTEGER NOT NULL
+                last_accessed INTEGER NOT NULL,
+                namespace TEXT NOT NULL DEFAULT 'global',
+                expires_at INTEGER,
+                review TEXT NOT NULL DEFAULT 'approved',
+                review_reason TEXT,
+                citations INTEGER NOT NULL DEFAULT 0,
+                cold_key TEXT,
+                cold_bytes INTEGER NOT NULL DEFAULT 0,
+                cold_reads INTEGER NOT NULL DEFAULT 0,
+                archived_into TEXT,
+                summary_of TEXT
             )
         "#)
-        .execute(&self.pool)
+        .execute(pool)
         .await
         .map_err(|e| Error::Other(anyhow::anyhow!("Failed to create memories table: {}", e)))?;
         
+        // Add namespace columns to databases created before namespaces existed
+        let columns: Vec<(String,)> = sqlx::query_as("SELECT name FROM pragma_table_info('memories')")
+            .fetch_all(pool)
+            .await
+            .map_err(|e| Error::Other(anyhow::anyhow!("Failed to inspect memories table: {}", e)))?;
+        if !columns.iter().any(|(name,)| name == "namespace") {
+            info!("Migrating memories table to namespaces");
+            sqlx::query("ALTER TABLE memories ADD COLUMN namespace TEXT NOT NULL DEFAULT 'global'")
+                .execute(pool)
+                .await
+                .map_err(|e| Error::Other(anyhow::anyhow!("Failed to add namespace column: {}", e)))?;
+            sqlx::query("UPDATE memories SET namespace = 'private:' || neuron_id")
+                .execute(pool)
+                .await
+                .map_err(|e| Error::Other(anyhow::anyhow!("Failed to assign namespaces: {}", e)))?;
+            sqlx::query("ALTER TABLE memories ADD COLUMN expires_at INTEGER")
+                .execute(pool)
+                .await
+                .map_err(|e| Error::Other(anyhow::anyhow!("Failed to add expires_at column: {}", e)))?;
+        }
+        
+        // Entries written before moderation existed are approved
+        if !columns.iter().any(|(name,)| name == "review") {
+            info!("Migrating memories table to review states");
+            sqlx::query("ALTER TABLE memories ADD COLUMN review TEXT NOT NULL DEFAULT 'approved'")
+                .execute(pool)
+                .await
+                .map_err(|e| Error::Other(anyhow::anyhow!("Failed to add review column: {}", e)))?;
+            sqlx::query("ALTER TABLE memories ADD COLUMN review_reason TEXT")
+                .execute(pool)
+                .await
+                .map_err(|e| Error::Other(anyhow::anyhow!("Failed to add review_reason column: {}", e)))?;
+        }
+        
+        // Entries written before citations were tracked have none
+        if !columns.iter().any(|(name,)| name == "citations") {
+            info!("Migrating memories table to citation counts");
+            sqlx::query("ALTER TABLE memories ADD COLUMN citations INTEGER NOT NULL DEFAULT 0")
+                .execute(pool)
+                .await
+                .map_err(|e| Error::Other(anyhow::anyhow!("Failed to add citations column: {}", e)))?;
+        }
+        
+        // Entries written before tiering existed are hot
+        if !columns.iter().any(|(name,)| name == "cold_key") {
+            info!("Migrating memories table to cold tiering");
+            sqlx::query("ALTER TABLE memories ADD COLUMN cold_key TEXT")
+                .execute(pool)
+                .await
+                .map_err(|e| Error::Other(anyhow::anyhow!("Failed to add cold_key column: {}", e)))?;
+            sqlx::query("ALTER TABLE memories ADD COLUMN cold_bytes INTEGER NOT NULL DEFAULT 0")
+                .execute(pool)
+                .await
+                .map_err(|e| Error::Other(anyhow::anyhow!("Failed to add cold_bytes column: {}", e)))?;
+            sqlx::query("ALTER TABLE memories ADD COLUMN cold_reads INTEGER NOT NULL DEFAULT 0")
+                .execute(pool)
+                .await
+                .map_err(|e| Error::Other(anyhow::anyhow!("Failed to add cold_reads column: {}", e)))?;
+        }
+        
+        // Entries written before summarization existed are not archived
+        if !columns.iter().any(|(name,)| name == "archived_into") {
+            info!("Migrating memories table to summaries");
+            sqlx::query("ALTER TABLE memories ADD COLUMN archived_into TEXT")
+                .execute(pool)
+                .await
+                .map_err(|e| Error::Other(anyhow::anyhow!("Failed to add archived_into column: {}", e)))?;
+            sqlx::query("ALTER TABLE memories ADD COLUMN summary_of TEXT")
+                .execute(pool)
+                .await
+                .map_err(|e| Error::Other(anyhow::anyhow!("Failed to add summary_of column: {}", e)))?;
+        }
+        
         // Create indexes
         sqlx::query("CREATE INDEX IF NOT EXISTS idx_neuron_id ON memories(neuron_id)")
-            .execute(&self.pool)
+            .execute(pool)
             .await
             .map_err(|e| Error::Other(anyhow::anyhow!("Failed to create neuron_id index: {}", e)))?;
             
         sqlx::query("CREATE INDEX IF NOT EXISTS idx_layer ON memories(layer)")
-            .execute(&self.pool)
+            .execute(pool)
             .await
             .map_err(|e| Error::Other(anyhow::anyhow!("Failed to create layer index: {}", e)))?;
             
         sqlx::query("CREATE INDEX IF NOT EXISTS idx_timestamp ON memories(timestamp)")
-            .execute(&self.pool)
+            .execute(pool)
             .await
             .map_err(|e| Error::Other(anyhow::anyhow!("Failed to create timestamp index: {}", e)))?;
             
         sqlx::query("CREATE INDEX IF NOT EXISTS idx_entry_type ON memories(entry_type)")
-            .execute(&self.pool)
+            .execute(pool)
             .await
             .map_err(|e| Error::Other(anyhow::anyhow!("Failed to create entry_type index: {}", e)))?;
             
         sqlx::query("CREATE INDEX IF NOT EXISTS idx_importance ON memories(importance)")
-            .execute(&self.pool)
+            .execute(pool)
             .await
             .map_err(|e| Error::Other(anyhow::anyhow!("Failed to create importance index: {}", e)))?;
+            
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_namespace ON memories(namespace)")
+            .execute(pool)
+            .await
+            .map_err(|e| Error::Other(anyhow::anyhow!("Failed to create namespace index: {}", e)))?;
+            
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_expires_at ON memories(expires_at)")
+            .execute(pool)
+            .await
+            .map_err(|e| Error::Other(anyhow::anyhow!("Failed to create expires_at index: {}", e)))?;
+            
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_review ON memories(review)")
+            .execute(pool)
+            .await
+            .map_err(|e| Error::Other(anyhow::anyhow!("Failed to create review index: {}", e)))?;
+            
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_cold_key ON memories(cold_key)")
+            .execute(pool)
+            .await
+            .map_err(|e| Error::Other(anyhow::anyhow!("Failed to create cold_key index: {}", e)))?;
+            
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_archived_into ON memories(archived_into)")
+            .execute(pool)
+            .await
+            .map_err(|e| Error::Other(anyhow::anyhow!("Failed to create archived_into index: {}", e)))?;
         
         // Create full-text search virtual table
         sqlx::query(r#"
@@ -131,7 +873,7 @@ impl MemoryStore for SqliteMemoryStore {
                 content_rowid=rowid
             )
         "#)
-        .execute(&self.pool)
+        .execute(pool)
         .await
         .map_err(|e| Error::Other(anyhow::anyhow!("Failed to create FTS table: {}", e)))?;
         
@@ -141,7 +883,7 @@ impl MemoryStore for SqliteMemoryStore {
                 INSERT INTO memories_fts(id, content) VALUES (new.id, new.content);
             END
         "#)
-        .execute(&self.pool)
+        .execute(pool)
         .await
         .map_err(|e| Error::Other(anyhow::anyhow!("Failed to create insert trigger: {}", e)))?;
         
@@ -150,7 +892,7 @@ impl MemoryStore for SqliteMemoryStore {
                 DELETE FROM memories_fts WHERE id = old.id;
             END
         "#)
-        .execute(&self.pool)
+        .execute(pool)
         .await
         .map_err(|e| Error::Other(anyhow::anyhow!("Failed to create delete trigger: {}", e)))?;
         
@@ -161,102 +903,83 @@ impl MemoryStore for SqliteMemoryStore {
     async fn store(&self, entry: MemoryEntry) -> Result<Uuid> {
         debug!("Storing memory entry: {} for neuron {}", entry.id, entry.neuron_id);
         
-        let timestamp = entry.timestamp.timestamp();
-        let last_accessed = entry.last_accessed.timestamp();
-        let metadata_json = serde_json::to_string(&entry.metadata)
-            .map_err(|e| Error::Serialization(e.to_string()))?;
-        let entry_type = serde_json::to_string(&entry.entry_type)
-            .map_err(|e| Error::Serialization(e.to_string()))?;
-        let embedding_bytes = entry.embedding.as_ref()
-            .map(|e| {
-                let bytes: Vec<u8> = e.iter()
-                    .flat_map(|f| f.to_le_bytes())
-                    .collect();
-                bytes
-            });
-            
-        sqlx::query(r#"
-            INSERT INTO memories (
-                id, neuron_id, layer, timestamp, entry_type, 
-                content, metadata, embedding, importance, 
-                access_count, last_accessed
-            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
-        "#)
-        .bind(entry.id.to_string())
-        .bind(&entry.neuron_id)
-        .bind(&entry.layer)
-        .bind(timestamp)
-        .bind(entry_type)
-        .bind(&entry.content)
-        .bind(metadata_json)
-        .bind(embedding_bytes)
-        .bind(entry.importance)
-        .bind(entry.access_count as i64)
-        .bind(last_accessed)
-        .execute(&self.pool)
+        let row = self.insert_row(&entry).await?;
+        let row = &row;
+        self.pools.write(|pool| async move {
+            row.query().execute(&pool).await
+        })
         .await
         .map_err(|e| Error::Other(anyhow::anyhow!("Failed to store memory: {}", e)))?;
         
         Ok(entry.id)
     }
     
-    async fn get(&self, id: Uuid) -> Result<Option<MemoryEntry>> {
-        let row = sqlx::query_as::<_, MemoryRow>(
-            r#"
-            SELECT id, neuron_id, layer, timestamp, entry_type,
-                   content, metadata, embedding, importance,
-                   access_count, last_accessed
-            FROM memories
-            WHERE id = ?
-            "#
-        )
-        .bind(id.to_string())
-        .fetch_optional(&self.pool)
+    async fn replace(&self, entry: MemoryEntry) -> Result<()> {
+        debug!("Replacing memory entry: {} for neuron {}", entry.id, entry.neuron_id);
+        
+        // Delete and insert rather than INSERT OR REPLACE, which would skip
+        // the delete trigger and leave a stale full-text row behind
+        let row = self.insert_row(&entry).await?;
+        let row = &row;
+        self.pools.write(|pool| async move {
+            let mut tx = pool.begin().await?;
+            sqlx::query("DELETE FROM memories WHERE id = ?")
+                .bind(&row.id)
+                .execute(&mut *tx)
+                .await?;
+            row.query().execute(&mut *tx).await?;
+            tx.commit().await
+        })
         .await
-        .map_err(|e| Error::Other(anyhow::anyhow!("Failed to get memory: {}", e)))?;
-        
-        match row {
-            Some(row) => {
-                let entry_type: MemoryType = serde_json::from_str(&row.entry_type)
-                    .map_err(|e| Error::Serialization(e.to_string()))?;
-                let metadata: serde_json::Value = serde_json::from_str(&row.metadata)
-                    .map_err(|e| Error::Serialization(e.to_string()))?;
-                let embedding = row.embedding.as_ref().map(|bytes| {
-                    bytes.chunks_exact(4)
-                        .map(|chunk| f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
-                        .collect()
-                });
-                
-                Ok(Some(MemoryEntry {
-                    id: Uuid::parse_str(&row.id)
-                        .map_err(|e| Error::Other(anyhow::anyhow!("Invalid UUID: {}", e)))?,
-                    neuron_id: row.neuron_id,
-                    layer: row.layer,
-                    timestamp: DateTime::from_timestamp(row.timestamp, 0)
-                        .unwrap_or_else(Utc::now),
-                    entry_type,
-                    content: row.content,
-                    metadata,
-                    embedding,
-                    importance: row.importance,
-                    access_count: row.access_count as u32,
-                    last_accessed: DateTime::from_timestamp(row.last_accessed, 0)
-                        .unwrap_or_else(Utc::now),
-                }))
-            }
-            None => Ok(None),
-        }
+        .map_err(|e| Error::Other(anyhow::anyhow!("Failed to replace memory: {}", e)))?;
+        
+        Ok(())
     }
     
-    async fn search(&self, params: MemorySearch) -> Result<Vec<MemoryEntry>> {
-        let mut query = String::from(
-            "SELECT id, neuron_id, layer, timestamp, entry_type, 
-                    content, metadata, embedding, importance,
-                    access_count, last_accessed
-             FROM memories WHERE 1=1"
+    async fn get(&self, id: Uuid) -> Result<Option<MemoryEntry>> {
+        let query = format!("SELECT {} FROM memories WHERE id = ?", MEMORY_COLUMNS);
+        let row = sqlx::query_as::<_, MemoryRow>(&query)
+            .bind(id.to_string())
+            .fetch_optional(self.pools.reader())
+            .await
+            .map_err(|e| Error::Other(anyhow::anyhow!("Failed to get memory: {}", e)))?;
+        
+        Ok(self.entries(row.into_iter().collect()).await?.pop())
+    }
+    
+    async fn export(&self, namespace: Option<&str>, neuron_id: Option<&str>) -> Result<Vec<MemoryEntry>> {
+        let query = format!(
+            "SELECT {} FROM memories
+             WHERE (expires_at IS NULL OR expires_at > ?)
+               AND review = 'approved'
+               AND (? IS NULL OR namespace = ?)
+               AND (? IS NULL OR neuron_id = ?)
+             ORDER BY timestamp, id",
+            MEMORY_COLUMNS
         );
+        let rows = sqlx::query_as::<_, MemoryRow>(&query)
+            .bind(Utc::now().timestamp())
+            .bind(namespace)
+            .bind(namespace)
+            .bind(neuron_id)
+            .bind(neuron_id)
+            .fetch_all(self.pools.reader())
+            .await
+            .map_err(|e| Error::Other(anyhow::anyhow!("Failed to export memories: {}", e)))?;
+        
+        // A dump reads every entry, which says nothing about how useful one is
+        self.read_entries(rows, false).await
+    }
+    
+    async fn search(&self, params: MemorySearch) -> Result<Vec<MemoryEntry>> {
+        // Expired entries are invisible even before the sweep removes them
+        let mut query = "(expires_at IS NULL OR expires_at > ?) AND review = ?".to_string();
+        let mut bindings = vec![Utc::now().timestamp().to_string(), params.review.as_str().to_string()];
         
-        let mut bindings = Vec::new();
+        if let Some(namespace) = &params.namespace {
+            query.push_str(" AND namespace = ?");
+            bindings.push(namespace.clone());
+        }
         
         if let Some(neuron_id) = &params.neuron_id {
             query.push_str(" AND neuron_id = ?");
@@ -290,79 +1013,205 @@ impl MemoryStore for SqliteMemoryStore {
             bindings.push(min_importance.to_string());
         }
         
+        let filters = query.clone();
+        let filter_bindings = bindings.clone();
+        
         // Handle content search
         if let Some(content_query) = &params.content_query {
             if params.use_semantic_search {
                 // TODO: Implement semantic search using embeddings
-                warn!("Semantic search not yet implemented, falling back to FTS");
+                warn!("Semantic search not yet implemented, falling back to substring match");
             }
-            
-            // Use FTS for text search
-            query = format!(
-                "SELECT m.* FROM memories m 
-                 JOIN memories_fts f ON m.id = f.id 
-                 WHERE memories_fts MATCH ? AND ({})",
-                query.replace("WHERE 1=1", "")
-            );
-            bindings.insert(0, content_query.clone());
+            if self.keyring.is_some() {
+                // Sealed content is searched through its embedding below
+                query.push_str(" AND content NOT LIKE 'tenc:%'");
+            }
+            query.push_str(" AND content LIKE ?");
+            bindings.push(format!("%{}%", content_query));
         }
         
-        query.push_str(" ORDER BY timestamp DESC LIMIT ?");
-        bindings.push(params.limit.to_string());
-        
-        // Build and execute dynamic query
-        use sqlx::sqlite::Sqlite;
-        let mut sql_query = sqlx::query::<Sqlite>(&query);
+        // An archived entry that matches stands for its summary
+        let query = format!(
+            "SELECT {} FROM memories
+             WHERE archived_into IS NULL
+               AND id IN (SELECT COALESCE(archived_into, id) FROM memories WHERE {})
+             ORDER BY timestamp DESC LIMIT ?",
+            MEMORY_COLUMNS, query
+        );
+        let mut sql_query = sqlx::query_as::<_, MemoryRow>(&query);
         for binding in bindings {
             sql_query = sql_query.bind(binding);
         }
+        let rows = sql_query
+            .bind(params.limit as i64)
+            .fetch_all(self.pools.reader())
+            .await
+            .map_err(|e| Error::Other(anyhow::anyhow!("Failed to search memories: {}", e)))?;
+        
+        let mut entries = self.entries(rows).await?;
+        if let (Some(content_query), Some(_)) = (&params.content_query, &self.keyring) {
+            entries.extend(self.search_sealed(&filters, &filter_bindings, content_query).await?);
+            entries.sort_by_key(|entry| std::cmp::Reverse(entry.timestamp));
+            entries.truncate(params.limit);
+        }
+        Ok(entries)
+    }
+    
+    async fn set_review(&self, id: Uuid, review: MemoryReview, reason: Option<&str>) -> Result<bool> {
+        let result = self.pools.write(|pool| async move {
+            sqlx::query("UPDATE memories SET review = ?, review_reason = ? WHERE id = ? AND review = 'pending'")
+                .bind(review.as_str())
+                .bind(reason)
+                .bind(id.to_string())
+                .execute(&pool)
+                .await
+        })
+        .await
+        .map_err(|e| Error::Other(anyhow::anyhow!("Failed to review memory: {}", e)))?;
         
-        // For now, return empty vector since dynamic queries are complex with sqlx
-        // TODO: Implement proper dynamic query building
-        warn!("Memory search not fully implemented yet");
-        Ok(Vec::new())
+        Ok(result.rows_affected() > 0)
     }
     
     async fn record_access(&self, id: Uuid) -> Result<()> {
         let now = Utc::now().timestamp();
         
-        sqlx::query(
-            "UPDATE memories 
-             SET access_count = access_count + 1, 
-                 last_accessed = ? 
-             WHERE id = ?"
-        )
-        .bind(now)
-        .bind(id.to_string())
-        .execute(&self.pool)
+        self.pools.write(|pool| async move {
+            sqlx::query(
+                "UPDATE memories 
+                 SET access_count = access_count + 1, 
+                     last_accessed = ? 
+                 WHERE id = ?"
+            )
+            .bind(now)
+            .bind(id.to_string())
+            .execute(&pool)
+            .await
+        })
         .await
         .map_err(|e| Error::Other(anyhow::anyhow!("Failed to record access: {}", e)))?;
         
         Ok(())
     }
     
+    async fn record_citation(&self, id: Uuid) -> Result<()> {
+        self.pools.write(|pool| async move {
+            sqlx::query("UPDATE memories SET citations = citations + 1 WHERE id = ?")
+                .bind(id.to_string())
+                .execute(&pool)
+                .await
+        })
+        .await
+        .map_err(|e| Error::Other(anyhow::anyhow!("Failed to record citation: {}", e)))?;
+        
+        Ok(())
+    }
+    
     async fn cleanup(&self, before: DateTime<Utc>, min_importance: f32) -> Result<u64> {
         let timestamp = before.timestamp();
         
-        let result = sqlx::query(
-            "DELETE FROM memories 
-             WHERE timestamp < ? AND importance < ?"
-        )
-        .bind(timestamp)
-        .bind(min_importance)
-        .execute(&self.pool)
+        let result = self.pools.write(|pool| async move {
+            sqlx::query(
+                "DELETE FROM memories 
+                 WHERE timestamp < ? AND importance < ? AND citations = 0"
+            )
+            .bind(timestamp)
+            .bind(min_importance)
+            .execute(&pool)
+            .await
+        })
         .await
         .map_err(|e| Error::Other(anyhow::anyhow!("Failed to cleanup memories: {}", e)))?;
         
         Ok(result.rows_affected())
     }
     
+    async fn delete_expired(&self, now: DateTime<Utc>) -> Result<u64> {
+        let result = self.pools
+            .write(|pool| async move {
+                sqlx::query("DELETE FROM memories WHERE expires_at IS NOT NULL AND expires_at <= ?")
+                    .bind(now.timestamp())
+                    .execute(&pool)
+                    .await
+            })
+            .await
+            .map_err(|e| Error::Other(anyhow::anyhow!("Failed to delete expired memories: {}", e)))?;
+        
+        Ok(result.rows_affected())
+    }
+    
+    async fn reseal(&self, limit: usize) -> Result<u64> {
+        let Some(keyring) = &self.keyring else {
+            return Ok(0);
+        };
+        let retired = keyring.retired_keys();
+        if retired.is_empty() {
+            return Ok(0);
+        }
+        
+        let query = format!(
+            "SELECT id, content FROM memories WHERE {} LIMIT ?",
+            vec!["content LIKE ?"; retired.len()].join(" OR ")
+        );
+        let mut sql_query = sqlx::query_as::<_, (String, String)>(&query);
+        for key_id in &retired {
+            sql_query = sql_query.bind(format!("{}%", encryption::sealed_prefix(key_id)));
+        }
+        let rows = sql_query
+            .bind(limit as i64)
+            .fetch_all(self.pools.reader())
+            .await
+            .map_err(|e| Error::Other(anyhow::anyhow!("Failed to find memories to reseal: {}", e)))?;
+        
+        let mut resealed = Vec::new();
+        for (id, content) in rows {
+            if let Some(new) = keyring.reseal(&content)? {
+                resealed.push((id, content, new));
+            }
+        }
+        if resealed.is_empty() {
+            return Ok(0);
+        }
+        self.store_resealed(&resealed).await
+    }
+    
+    async fn namespace_usage(&self, namespace: Option<&str>) -> Result<Vec<NamespaceUsage>> {
+        let rows: Vec<(String, i64, i64, i64, i64, i64)> = sqlx::query_as(
+            "SELECT namespace,
+                    COALESCE(SUM(review <> 'rejected' AND archived_into IS NULL), 0),
+                    COALESCE(SUM(CASE WHEN review <> 'rejected' AND archived_into IS NULL
+                                      THEN LENGTH(CAST(content AS BLOB)) + cold_bytes ELSE 0 END), 0),
+                    COALESCE(SUM(review = 'pending'), 0),
+                    COALESCE(SUM(review = 'rejected'), 0),
+                    COALESCE(SUM(archived_into IS NOT NULL), 0)
+             FROM memories
+             WHERE (? IS NULL OR namespace = ?)
+             GROUP BY namespace
+             ORDER BY namespace"
+        )
+        .bind(namespace)
+        .bind(namespace)
+        .fetch_all(self.pools.reader())
+        .await
+        .map_err(|e| Error::Other(anyhow::anyhow!("Failed to get namespace usage: {}", e)))?;
+        
+        Ok(rows.into_iter()
+            .map(|(namespace, entries, bytes, pending, rejected, archived)| NamespaceUsage {
+                namespace,
+                entries: entries as u64,
+                bytes: bytes as u64,
+                pending: pending as u64,
+                rejected: rejected as u64,
+                archived: archived as u64,
+            })
+            .collect())
+    }
+    
     async fn get_stats(&self, neuron_id: &str) -> Result<MemoryStats> {
         let row: (i64,) = sqlx::query_as(
             "SELECT COUNT(*) FROM memories WHERE neuron_id = ?"
         )
         .bind(neuron_id)
-        .fetch_one(&self.pool)
+        .fetch_one(self.pools.reader())
         .await
         .map_err(|e| Error::Other(anyhow::anyhow!("Failed to get total count: {}", e)))?;
         
@@ -0,0 +1,179 @@
+//! Summaries of similar memory entries
+//!
+//! A namespace near its quota would start refusing writes, and deleting its
+//! oldest entries throws their lessons away. Instead the entries are
+//! clustered by the similarity of their embeddings, a [`MemorySummarizer`]
+//! writes one summary entry per cluster, and the entries of the cluster are
+//! archived into it. The summary lists the entries it was written from in
+//! `summary_of` and each of them names the summary in `archived_into`, both
+//! stored with the summary in one transaction by
+//! [`SqliteMemoryStore::store_summary`].
+//!
+//! An archived entry no longer counts against its namespace's quota and no
+//! search returns it: a search it matches returns its summary instead.
+//! Content searches only match content in the hot tier, so once archived
+//! entries move to the cold tier prompts recall their summary by its own
+//! content. Reading an archived
+//! entry by its ID still returns it, content and all, wherever its content
+//! is kept. Summaries are never clustered again.
+//!
+//! [`SqliteMemoryStore::store_summary`]: super::SqliteMemoryStore::store_summary
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use super::{EmbeddingGenerator, MemoryEntry, MemoryReview, MemoryType};
+use crate::metadata_schema::keys;
+use crate::Result;
+
+/// Neuron and layer of a summary whose entries came from several
+pub const SUMMARIZER: &str = "memory-summarizer";
+
+/// A summary of a cluster of entries and what writing it cost
+#[derive(Debug, Clone, PartialEq)]
+pub struct Summary {
+    pub content: String,
+    pub cost_usd: f64,
+}
+
+/// Writes the summary of a cluster of similar entries
+#[async_trait]
+pub trait MemorySummarizer: Send + Sync {
+    /// One entry's worth of content stating what `entries`, oldest first,
+    /// say together
+    async fn summarize(&self, entries: &[MemoryEntry]) -> Result<Summary>;
+}
+
+fn org_of(entry: &MemoryEntry) -> Option<&str> {
+    entry.metadata.get(keys::auth::ORG_ID).and_then(|org| org.as_str())
+}
+
+/// Clusters of `entries`, as indexes into it, given each entry's embedding.
+/// Entries join, in order, the cluster of the same organization whose
+/// centroid they are most similar to, if at least `min_similarity` and the
+/// cluster has fewer than `max_size` entries, else start their own.
+pub fn cluster(entries: &[MemoryEntry], embeddings: &[Vec<f32>], min_similarity: f32, max_size: usize) -> Vec<Vec<usize>> {
+    let mut clusters: Vec<(Vec<usize>, Vec<f32>)> = Vec::new();
+    for (i, (entry, embedding)) in entries.iter().zip(embeddings).enumerate() {
+        let best = clusters.iter()
+            .enumerate()
+            .filter(|(_, (members, _))| members.len() < max_size && org_of(&entries[members[0]]) == org_of(entry))
+            .map(|(c, (_, sum))| (c, EmbeddingGenerator::cosine_similarity(sum, embedding)))
+            .filter(|(_, similarity)| *similarity >= min_similarity)
+            .max_by(|a, b| a.1.total_cmp(&b.1));
+        match best {
+            Some((c, _)) => {
+                let (members, sum) = &mut clusters[c];
+                members.push(i);
+                // The sum points where the centroid does
+                for (total, value) in sum.iter_mut().zip(embedding) {
+                    *total += value;
+                }
+            }
+            None => clusters.push((vec![i], embedding.clone())),
+        }
+    }
+    clusters.into_iter().map(|(members, _)| members).collect()
+}
+
+/// The one value of `field` all `entries` share
+fn shared<'a, T: PartialEq + ?Sized>(entries: &'a [MemoryEntry], field: impl Fn(&'a MemoryEntry) -> &'a T) -> Option<&'a T> {
+    let first = field(entries.first()?);
+    entries.iter().all(|entry| field(entry) == first).then_some(first)
+}
+
+/// Summary entry with `content` for `entries`, oldest first, all of one
+/// namespace and organization. It is as recent and important as the most
+/// recent and important of them, expires with the last of them and
+/// inherits their citations.
+pub fn summary_entry(entries: &[MemoryEntry], content: String, now: DateTime<Utc>) -> MemoryEntry {
+    let mut metadata = serde_json::json!({});
+    if let Some(org_id) = entries.first().and_then(org_of) {
+        metadata[keys::auth::ORG_ID] = org_id.into();
+    }
+    let expires_at = entries.iter()
+        .map(|entry| entry.expires_at)
+        .try_fold(None, |latest: Option<DateTime<Utc>>, expires_at| expires_at.map(|at| latest.max(Some(at))));
+    MemoryEntry {
+        id: Uuid::new_v4(),
+        neuron_id: shared(entries, |e| e.neuron_id.as_str()).unwrap_or(SUMMARIZER).to_string(),
+        layer: shared(entries, |e| e.layer.as_str()).unwrap_or(SUMMARIZER).to_string(),
+        timestamp: entries.iter().map(|entry| entry.timestamp).max().unwrap_or(now),
+        entry_type: shared(entries, |e| &e.entry_type).cloned().unwrap_or(MemoryType::Learning),
+        content,
+        metadata,
+        embedding: None,
+        importance: entries.iter().map(|entry| entry.importance).fold(0.0, f32::max),
+        access_count: 0,
+        last_accessed: now,
+        namespace: entries.first().map(|entry| entry.namespace.clone()).unwrap_or_default(),
+        expires_at: expires_at.flatten(),
+        review: MemoryReview::Approved,
+        review_reason: None,
+        citations: entries.iter().map(|entry| entry.citations).sum(),
+        archived_into: None,
+        summary_of: entries.iter().map(|entry| entry.id).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::MemoryBuilder;
+
+    fn entry(content: &str, org_id: Option<&str>) -> MemoryEntry {
+        let metadata = match org_id {
+            Some(org_id) => serde_json::json!({ keys::auth::ORG_ID: org_id }),
+            None => serde_json::json!({}),
+        };
+        MemoryBuilder::new("n1".to_string(), "L3".to_string())
+            .with_content(content.to_string())
+            .with_metadata(metadata)
+            .build()
+    }
+
+    #[test]
+    fn test_cluster_groups_similar_entries_of_one_org() {
+        let entries = vec![
+            entry("a", None),
+            entry("b", None),
+            entry("c", Some("acme")),
+            entry("d", None),
+            entry("e", None),
+        ];
+        let embeddings = vec![
+            vec![1.0, 0.0],
+            vec![0.0, 1.0],
+            vec![1.0, 0.0],
+            vec![0.9, 0.1],
+            vec![1.0, 0.05],
+        ];
+
+        assert_eq!(cluster(&entries, &embeddings, 0.9, 10), vec![vec![0, 3, 4], vec![1], vec![2]]);
+        // A full cluster takes no more entries
+        assert_eq!(cluster(&entries, &embeddings, 0.9, 2), vec![vec![0, 3], vec![1], vec![2], vec![4]]);
+    }
+
+    #[test]
+    fn test_summary_entry_inherits_from_its_entries() {
+        let now = Utc::now();
+        let mut entries = vec![entry("a", Some("acme")), entry("b", Some("acme"))];
+        entries[0].importance = 0.9;
+        entries[0].citations = 2;
+        entries[1].citations = 1;
+        entries[1].neuron_id = "n2".to_string();
+        entries[0].expires_at = Some(now);
+        entries[1].expires_at = Some(now + chrono::Duration::hours(1));
+
+        let summary = summary_entry(&entries, "a and b".to_string(), now);
+        assert_eq!(summary.summary_of, vec![entries[0].id, entries[1].id]);
+        assert_eq!((summary.neuron_id.as_str(), summary.layer.as_str()), (SUMMARIZER, "L3"));
+        assert_eq!((summary.importance, summary.citations), (0.9, 3));
+        assert_eq!(summary.expires_at, entries[1].expires_at);
+        assert_eq!(summary.metadata[keys::auth::ORG_ID], "acme");
+
+        entries[0].expires_at = None;
+        assert_eq!(summary_entry(&entries, String::new(), now).expires_at, None);
+    }
+}
@@ -0,0 +1,229 @@
+//! Cold tier for memory content
+//!
+//! Entries nobody reads for a while still sit in the memory database and
+//! every backup of it. Moving one to the cold tier writes its content to an
+//! [`ObjectStore`] and leaves a stub row behind: every column but the
+//! content, which is emptied, and the key of the object holding it. Filters
+//! and embedding searches still find a cold entry; substring searches do
+//! not. Reading one fetches its content from the object store, so it is
+//! slower, and an entry read often enough is promoted back to the hot tier.
+//! Writes always go to the hot tier. Sealed content stays sealed in the
+//! cold tier under the key it was sealed with, so a retired tenant key is
+//! needed until the entries sealed under it are promoted.
+//!
+//! A move never leaves an entry in zero or two places. Demotion writes the
+//! object first and then swaps the row for a stub in one statement that only
+//! matches the row as it was read; promotion puts the content back in the
+//! row before deleting the object. An interrupted move, like a deleted or
+//! replaced cold entry, leaves an object no row refers to, which
+//! [`SqliteMemoryStore::reconcile_cold`] deletes. Until then the row alone
+//! decides which tier the entry is in.
+//!
+//! [`SqliteMemoryStore::reconcile_cold`]: super::SqliteMemoryStore::reconcile_cold
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{Error, Result};
+
+/// Prefix of the objects holding memory content
+pub const MEMORY_PREFIX: &str = "memories/";
+
+/// Blob storage the cold tier is kept in
+#[async_trait]
+pub trait ObjectStore: Send + Sync {
+    /// Kind of store, e.g. "filesystem"
+    fn kind(&self) -> &'static str;
+
+    /// Write `bytes` under `key`, durably, replacing any object there
+    async fn put(&self, key: &str, bytes: Vec<u8>) -> Result<()>;
+
+    /// Object under `key`, if there is one
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>>;
+
+    /// Delete the object under `key`; deleting a missing object succeeds
+    async fn delete(&self, key: &str) -> Result<()>;
+
+    /// Keys of every object starting with `prefix`
+    async fn list(&self, prefix: &str) -> Result<Vec<String>>;
+}
+
+/// Objects as files under a directory, keys as relative paths
+#[derive(Debug, Clone)]
+pub struct FsObjectStore {
+    dir: PathBuf,
+}
+
+impl FsObjectStore {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    fn path(&self, key: &str) -> Result<PathBuf> {
+        if key.is_empty() || key.split('/').any(|part| part.is_empty() || part == "." || part == "..") {
+            return Err(Error::InvalidInput(format!("Invalid object key '{}'", key)));
+        }
+        Ok(self.dir.join(key))
+    }
+
+    fn list_dir(dir: &Path, key: &str, keys: &mut Vec<String>) -> std::io::Result<()> {
+        let entries = match std::fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e),
+        };
+        for entry in entries {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let child = if key.is_empty() { name } else { format!("{}/{}", key, name) };
+            if entry.file_type()?.is_dir() {
+                Self::list_dir(&entry.path(), &child, keys)?;
+            } else if !child.ends_with(".partial") {
+                keys.push(child);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl ObjectStore for FsObjectStore {
+    fn kind(&self) -> &'static str {
+        "filesystem"
+    }
+
+    async fn put(&self, key: &str, bytes: Vec<u8>) -> Result<()> {
+        let path = self.path(key)?;
+        tokio::task::spawn_blocking(move || -> std::io::Result<()> {
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            // Only a complete, synced file takes the key's name
+            let partial = path.with_extension("partial");
+            let mut file = std::fs::File::create(&partial)?;
+            std::io::Write::write_all(&mut file, &bytes)?;
+            file.sync_all()?;
+            std::fs::rename(&partial, &path)
+        })
+        .await
+        .map_err(|e| Error::Other(anyhow::anyhow!("Cold tier writer panicked: {}", e)))?
+        .map_err(Error::Io)
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        match tokio::fs::read(self.path(key)?).await {
+            Ok(bytes) => Ok(Some(bytes)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(Error::Io(e)),
+        }
+    }
+
+    async fn delete(&self, key: &str) -> Result<()> {
+        match tokio::fs::remove_file(self.path(key)?).await {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(Error::Io(e)),
+            _ => Ok(()),
+        }
+    }
+
+    async fn list(&self, prefix: &str) -> Result<Vec<String>> {
+        let dir = self.dir.clone();
+        let prefix = prefix.to_string();
+        tokio::task::spawn_blocking(move || {
+            let mut keys = Vec::new();
+            Self::list_dir(&dir, "", &mut keys)?;
+            keys.retain(|key| key.starts_with(&prefix));
+            keys.sort();
+            Ok(keys)
+        })
+        .await
+        .map_err(|e| Error::Other(anyhow::anyhow!("Cold tier lister panicked: {}", e)))?
+        .map_err(Error::Io)
+    }
+}
+
+/// Key a new copy of entry `id`'s content is written under. Every move gets
+/// its own key, so an object left by an earlier move is never mistaken for
+/// the current one.
+pub fn object_key(id: &str) -> String {
+    format!("{}{}/{}", MEMORY_PREFIX, id, Uuid::new_v4().simple())
+}
+
+/// Where cold content is kept, and counts of its reads
+pub struct ColdTier {
+    store: Arc<dyn ObjectStore>,
+    promote_after_reads: u32,
+    reads: AtomicU64,
+    promotions: AtomicU64,
+    /// Held while an object is written but not yet referred to, so
+    /// reconciliation never takes it for an orphan
+    pub(crate) moving: tokio::sync::Mutex<()>,
+}
+
+impl ColdTier {
+    /// A tier in `store`, promoting an entry back once it has been read
+    /// `promote_after_reads` times while cold; 0 never promotes
+    pub fn new(store: Arc<dyn ObjectStore>, promote_after_reads: u32) -> Self {
+        Self {
+            store,
+            promote_after_reads,
+            reads: AtomicU64::new(0),
+            promotions: AtomicU64::new(0),
+            moving: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    pub fn store(&self) -> &dyn ObjectStore {
+        self.store.as_ref()
+    }
+
+    /// Whether an entry read `reads` times while cold goes back to the hot
+    /// tier
+    pub(crate) fn should_promote(&self, reads: i64) -> bool {
+        self.promote_after_reads > 0 && reads >= self.promote_after_reads as i64
+    }
+
+    pub(crate) fn record_read(&self) {
+        self.reads.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_promotion(&self) {
+        self.promotions.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn stats(&self) -> ColdTierStats {
+        ColdTierStats {
+            backend: self.store.kind().to_string(),
+            reads: self.reads.load(Ordering::Relaxed),
+            promotions: self.promotions.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Reads served from the cold tier since startup
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ColdTierStats {
+    pub backend: String,
+    /// Entries whose content was fetched from the cold tier
+    pub reads: u64,
+    /// Entries moved back to the hot tier for being read often
+    pub promotions: u64,
+}
+
+/// Entries in one tier and the size of their content
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct TierSize {
+    pub entries: u64,
+    pub bytes: u64,
+}
+
+/// Size of each tier
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct TierUsage {
+    pub hot: TierSize,
+    pub cold: TierSize,
+}
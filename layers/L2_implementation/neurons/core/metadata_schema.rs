@@ -0,0 +1,592 @@
+//! Schema registry for signal metadata keys
+//!
+//! Metadata keys are namespaced (`trace.id`, `experiment.variant`,
+//! `cost.model`) and every key declares a value type and the component that
+//! owns it. Rust code refers to keys through the generated [`keys`] module so
+//! a typo is a compile error rather than a silently dropped value.
+
+use std::collections::{BTreeMap, HashMap};
+
+use chrono::DateTime;
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use serde::{Deserialize, Deserializer, Serialize};
+use uuid::Uuid;
+
+use crate::{Error, Result};
+
+/// Type a metadata value must parse as
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+pub enum MetadataType {
+    String,
+    Integer,
+    Float,
+    Bool,
+    Uuid,
+    Timestamp,
+}
+
+impl MetadataType {
+    /// Whether `value` is a valid encoding of this type
+    pub fn accepts(&self, value: &str) -> bool {
+        match self {
+            MetadataType::String => true,
+            MetadataType::Integer => value.parse::<i64>().is_ok(),
+            MetadataType::Float => value.parse::<f64>().map(f64::is_finite).unwrap_or(false),
+            MetadataType::Bool => matches!(value, "true" | "false"),
+            MetadataType::Uuid => Uuid::parse_str(value).is_ok(),
+            MetadataType::Timestamp => DateTime::parse_from_rfc3339(value).is_ok(),
+        }
+    }
+}
+
+/// How keys outside the schema are treated
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+pub enum ValidationMode {
+    /// Unknown namespaces and keys are rejected
+    Strict,
+    /// Unknown namespaces and keys are logged and accepted
+    #[default]
+    Permissive,
+}
+
+/// A registered metadata key
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+pub struct KeySpec {
+    /// Full key including its namespace, e.g. `trace.chain_id`
+    pub key: String,
+    pub value_type: MetadataType,
+    /// Component responsible for writing the key
+    pub owner: String,
+    pub description: String,
+    /// Ad-hoc key used for the same value before namespacing
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub legacy_key: Option<String>,
+}
+
+impl KeySpec {
+    /// Namespace part of the key
+    pub fn namespace(&self) -> &str {
+        namespace_of(&self.key).unwrap_or("")
+    }
+}
+
+/// Why a metadata entry failed validation
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+pub enum MetadataViolation {
+    UnknownNamespace { key: String },
+    UnknownKey { key: String },
+    InvalidValue { key: String, expected: MetadataType, value: String },
+}
+
+impl MetadataViolation {
+    /// Type violations are errors regardless of the validation mode
+    pub fn is_type_error(&self) -> bool {
+        matches!(self, MetadataViolation::InvalidValue { .. })
+    }
+}
+
+impl std::fmt::Display for MetadataViolation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MetadataViolation::UnknownNamespace { key } => {
+                write!(f, "metadata key '{}' is not in a registered namespace", key)
+            }
+            MetadataViolation::UnknownKey { key } => write!(f, "metadata key '{}' is not registered", key),
+            MetadataViolation::InvalidValue { key, expected, value } => {
+                write!(f, "metadata key '{}' expects {:?}, got '{}'", key, expected, value)
+            }
+        }
+    }
+}
+
+/// Schema as served to clients
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+pub struct SchemaDescription {
+    pub mode: ValidationMode,
+    pub namespaces: Vec<NamespaceDescription>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+pub struct NamespaceDescription {
+    pub name: String,
+    pub owner: String,
+    pub keys: Vec<KeySpec>,
+}
+
+/// Declares the builtin namespaces, generating one constants module per
+/// namespace under [`keys`] alongside the matching [`KeySpec`]s
+macro_rules! metadata_schema {
+    ($(
+        $ns:ident owned_by $owner:literal {
+            $( $konst:ident = $name:literal : $ty:ident, $desc:literal $(, legacy $legacy:literal)? ; )*
+        }
+    )*) => {
+        /// Every builtin metadata key, grouped by namespace
+        pub mod keys {
+            $(
+                #[doc = concat!("Keys in the `", stringify!($ns), "` namespace, owned by ", $owner)]
+                pub mod $ns {
+                    $(
+                        #[doc = $desc]
+                        pub const $konst: &str = concat!(stringify!($ns), ".", $name);
+                    )*
+                }
+            )*
+        }
+
+        fn builtin_namespaces() -> Vec<(&'static str, &'static str)> {
+            vec![$((stringify!($ns), $owner)),*]
+        }
+
+        fn builtin_keys() -> Vec<KeySpec> {
+            vec![$($(
+                KeySpec {
+                    key: keys::$ns::$konst.to_string(),
+                    value_type: MetadataType::$ty,
+                    owner: $owner.to_string(),
+                    description: $desc.to_string(),
+                    legacy_key: None$(.or(Some($legacy.to_string())))?,
+                },
+            )*)*]
+        }
+    };
+}
+
+metadata_schema! {
+    trace owned_by "tracing" {
+        ID = "id": String, "Distributed trace the signal belongs to";
+        CHAIN_ID = "chain_id": String, "Root signal of the chain this signal was derived from", legacy "chain_id";
+        PARENT_ID = "parent_id": Uuid, "Signal whose processing produced this one";
+        REPLAY_OF = "replay_of": String, "Chain this chain was replayed from";
+    }
+    auth owned_by "auth" {
+        USER_ID = "user_id": String, "User the chain is attributed to", legacy "user_id";
+        ORG_ID = "org_id": String, "Organization the chain is attributed to", legacy "org_id";
+        API_KEY_ID = "api_key_id": String, "API key the chain was started with";
+    }
+    network owned_by "network" {
+        PEER_IDENTITY = "peer_identity": String, "Certificate identity of the peer that delivered the signal", legacy "peer_identity";
+        FROM_SERVER = "from_server": String, "Server that forwarded the signal", legacy "from_server";
+        VIA_SERVER = "via_server": String, "Server the signal was last relayed through", legacy "via_server";
+        HOP_COUNT = "hop_count": Integer, "Number of servers the signal has crossed", legacy "hop_count";
+        PRIORITY = "priority": String, "Transport priority; \"high\" signals are sent to peers without waiting for a batch";
+    }
+    routing owned_by "router" {
+        SHED_FROM = "shed_from": String, "Neuron that shed the signal to its fallback", legacy "shed_from";
+        TARGET_LAYER = "target_layer": String, "Hierarchical layer the signal is addressed to", legacy "target_layer";
+        IS_QUERY = "is_query": Bool, "Whether the signal expects a response", legacy "is_query";
+        IS_CONTROL = "is_control": Bool, "Whether the signal carries a control message", legacy "is_control";
+        DEADLINE = "deadline": Timestamp, "Time by which the signal should be processed; local model requests close to it skip batching";
+        SPECULATIVE_OF = "speculative_of": Uuid, "Parent whose streamed output started the signal before the parent finished; removed once the parent confirms it";
+        GROUP = "group": String, "Routing group a forward was addressed to, or \"*\" when it named no neuron";
+        ROUTE = "route": String, "Neuron the sender's routing policy chose for the forward";
+        STRATEGY = "strategy": String, "Strategy of the routing policy that chose the route";
+        RULE = "rule": String, "Content match rule that chose the route, or \"fallback\" when none matched";
+    }
+    validation owned_by "validation" {
+        STATUS = "status": String, "Outcome of response validation", legacy "validation_status";
+        REASON = "reason": String, "Why validation failed", legacy "validation_reason";
+        RETRIES = "retries": Integer, "Validation retries spent on the response", legacy "validation_retries";
+    }
+    recovery owned_by "recovery" {
+        STEPS = "steps": String, "Recovery steps attempted after the neuron failed, as step:outcome pairs in order";
+        OUTCOME = "outcome": String, "How recovery ended: recovered, degraded, dead_lettered or exhausted";
+        ERROR = "error": String, "Error that started recovery";
+    }
+    output owned_by "output_format" {
+        FORMAT = "format": String, "Format the chain's final output is requested in: markdown, json or text";
+        SCHEMA = "schema": String, "JSON Schema a json final output must match";
+    }
+    experiment owned_by "experiments" {
+        ID = "id": String, "Experiment the signal is enrolled in";
+        VARIANT = "variant": String, "Experiment variant the signal was assigned";
+    }
+    cost owned_by "cost_tracking" {
+        MODEL = "model": String, "Model that produced the response";
+        TOKENS = "tokens": Integer, "Tokens spent producing the response";
+        USD = "usd": Float, "Cost of the response in US dollars";
+        TAGS = "tags": String, "Cost attribution tags of the chain, as sorted key=value pairs separated by commas";
+    }
+    model owned_by "models" {
+        REQUESTED = "requested": String, "Retired model the neuron is configured with, when its call was remapped";
+        REMAPPED_TO = "remapped_to": String, "Model that served the call in its place";
+    }
+    timeout owned_by "timeouts" {
+        MS = "ms": Integer, "Timeout of the provider call that produced the signal, in milliseconds";
+        SOURCE = "source": String, "How that timeout was chosen: default, layer or adaptive";
+        PERCENTILE_MS = "percentile_ms": Integer, "Latency percentile an adaptive timeout was scaled from, in milliseconds";
+    }
+    fan_out owned_by "fan_out" {
+        BUDGET = "budget": Integer, "Signals the chain may still process in the subtree of this signal, the signal included";
+    }
+    gradient owned_by "learning" {
+        ERROR_CLASS = "error_class": String, "Class of the error a backward signal reports, e.g. timeout or validation";
+        ATTENUATION = "attenuation": Float, "Factor the gradient's magnitude was scaled by when the backward signal was sent";
+    }
+    memory owned_by "memory" {
+        CITED = "cited": String, "Memory entries the response that produced the signal cited, as comma-separated entry IDs";
+    }
+    goal owned_by "goals" {
+        ID = "id": Uuid, "Goal the chain works towards";
+    }
+    ingest owned_by "ingestion" {
+        SOURCE = "source": String, "Ingestion source the chain's message arrived through";
+        MESSAGE_KEY = "message_key": String, "Key the message was deduplicated by";
+    }
+    prompt owned_by "prompt_composition" {
+        SECTIONS = "sections": String, "Prompt tokens of the provider call that produced the signal by section, as section=tokens pairs separated by commas";
+        TOKENIZER = "tokenizer": String, "Tokenizer the prompt sections were counted with";
+    }
+    shadow owned_by "shadowing" {
+        OF = "of": String, "Production chain whose submission the chain shadows, on the staging server it was sent to";
+    }
+    template owned_by "templates" {
+        NAME = "name": String, "Chain template the chain's content was rendered from";
+        VERSION = "version": Integer, "Version of that template the submission was pinned to";
+    }
+    session owned_by "sessions" {
+        ID = "id": String, "Session the chain is a turn of";
+        CONTEXT = "context": String, "The session's conversation so far as the chain's prompts show it; only on the root signal";
+    }
+    game owned_by "game_neurons" {
+        EVENT = "event": String, "Game event the signal reports", legacy "game_event";
+        SOURCE = "source": String, "Subsystem the game signal came from", legacy "source";
+    }
+}
+
+fn namespace_of(key: &str) -> Option<&str> {
+    key.split_once('.').map(|(namespace, _)| namespace).filter(|ns| !ns.is_empty())
+}
+
+/// Registered namespaces and keys
+#[derive(Debug, Clone)]
+pub struct MetadataSchema {
+    mode: ValidationMode,
+    /// Namespace name to owner
+    namespaces: BTreeMap<String, String>,
+    keys: BTreeMap<String, KeySpec>,
+    /// Legacy key to namespaced key
+    legacy: HashMap<String, String>,
+}
+
+impl MetadataSchema {
+    /// An empty schema
+    pub fn new(mode: ValidationMode) -> Self {
+        Self {
+            mode,
+            namespaces: BTreeMap::new(),
+            keys: BTreeMap::new(),
+            legacy: HashMap::new(),
+        }
+    }
+
+    /// The schema of every key in [`keys`]
+    pub fn builtin(mode: ValidationMode) -> Self {
+        let mut schema = Self::new(mode);
+        for (namespace, owner) in builtin_namespaces() {
+            schema.register_namespace(namespace, owner).expect("builtin namespaces are unique");
+        }
+        for spec in builtin_keys() {
+            schema.register(spec).expect("builtin keys are unique");
+        }
+        schema
+    }
+
+    pub fn mode(&self) -> ValidationMode {
+        self.mode
+    }
+
+    pub fn set_mode(&mut self, mode: ValidationMode) {
+        self.mode = mode;
+    }
+
+    /// Claim a namespace for `owner`
+    pub fn register_namespace(&mut self, namespace: &str, owner: &str) -> Result<()> {
+        if namespace.is_empty() || namespace.contains('.') {
+            return Err(Error::InvalidInput(format!("Invalid metadata namespace '{}'", namespace)));
+        }
+        match self.namespaces.get(namespace) {
+            Some(existing) if existing != owner => Err(Error::InvalidInput(format!(
+                "Metadata namespace '{}' is already owned by {}",
+                namespace, existing
+            ))),
+            Some(_) => Ok(()),
+            None => {
+                self.namespaces.insert(namespace.to_string(), owner.to_string());
+                Ok(())
+            }
+        }
+    }
+
+    /// Register a key in a namespace its owner has claimed
+    pub fn register(&mut self, spec: KeySpec) -> Result<()> {
+        let namespace = namespace_of(&spec.key)
+            .ok_or_else(|| Error::InvalidInput(format!("Metadata key '{}' has no namespace", spec.key)))?;
+        match self.namespaces.get(namespace) {
+            None => {
+                return Err(Error::InvalidInput(format!(
+                    "Metadata namespace '{}' is not registered",
+                    namespace
+                )))
+            }
+            Some(owner) if *owner != spec.owner => {
+                return Err(Error::InvalidInput(format!(
+                    "Metadata namespace '{}' is owned by {}, not {}",
+                    namespace, owner, spec.owner
+                )))
+            }
+            Some(_) => {}
+        }
+        if self.keys.contains_key(&spec.key) {
+            return Err(Error::InvalidInput(format!("Metadata key '{}' is already registered", spec.key)));
+        }
+        if let Some(legacy) = &spec.legacy_key {
+            if let Some(existing) = self.legacy.get(legacy) {
+                return Err(Error::InvalidInput(format!(
+                    "Legacy metadata key '{}' already maps to '{}'",
+                    legacy, existing
+                )));
+            }
+            self.legacy.insert(legacy.clone(), spec.key.clone());
+        }
+        self.keys.insert(spec.key.clone(), spec);
+        Ok(())
+    }
+
+    pub fn get(&self, key: &str) -> Option<&KeySpec> {
+        self.keys.get(key)
+    }
+
+    /// Every violation in `metadata`, regardless of mode
+    pub fn check(&self, metadata: &HashMap<String, String>) -> Vec<MetadataViolation> {
+        let mut violations: Vec<_> = metadata
+            .iter()
+            .filter_map(|(key, value)| match self.keys.get(key) {
+                Some(spec) if !spec.value_type.accepts(value) => Some(MetadataViolation::InvalidValue {
+                    key: key.clone(),
+                    expected: spec.value_type,
+                    value: value.clone(),
+                }),
+                Some(_) => None,
+                None => match namespace_of(key) {
+                    Some(namespace) if self.namespaces.contains_key(namespace) => {
+                        Some(MetadataViolation::UnknownKey { key: key.clone() })
+                    }
+                    _ => Some(MetadataViolation::UnknownNamespace { key: key.clone() }),
+                },
+            })
+            .collect();
+        violations.sort_by_key(|violation| violation.to_string());
+        violations
+    }
+
+    /// Validate metadata attached to a new signal. Type violations always
+    /// fail; unknown keys fail in strict mode and are logged otherwise.
+    pub fn validate(&self, metadata: &HashMap<String, String>) -> Result<()> {
+        let violations = self.check(metadata);
+        let (errors, warnings): (Vec<_>, Vec<_>) = violations
+            .into_iter()
+            .partition(|violation| violation.is_type_error() || self.mode == ValidationMode::Strict);
+        for warning in &warnings {
+            tracing::warn!("Accepting unregistered signal metadata: {}", warning);
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            let errors: Vec<_> = errors.iter().map(ToString::to_string).collect();
+            Err(Error::InvalidInput(errors.join("; ")))
+        }
+    }
+
+    /// Rename known ad-hoc keys to their namespaced form, returning how many
+    /// were moved. A namespaced value already present wins over the legacy one.
+    pub fn migrate_legacy(&self, metadata: &mut HashMap<String, String>) -> usize {
+        let legacy: Vec<_> = metadata
+            .keys()
+            .filter_map(|key| self.legacy.get(key).map(|namespaced| (key.clone(), namespaced.clone())))
+            .collect();
+        for (old, new) in &legacy {
+            if let Some(value) = metadata.remove(old) {
+                metadata.entry(new.clone()).or_insert(value);
+            }
+        }
+        legacy.len()
+    }
+
+    /// Rename namespaced keys back to their ad-hoc form, for readers that
+    /// predate namespacing, returning how many were moved
+    pub fn restore_legacy(&self, metadata: &mut HashMap<String, String>) -> usize {
+        let mut moved = 0;
+        for (legacy, namespaced) in &self.legacy {
+            if let Some(value) = metadata.remove(namespaced) {
+                metadata.insert(legacy.clone(), value);
+                moved += 1;
+            }
+        }
+        moved
+    }
+
+    /// Namespaces and keys for client discovery
+    pub fn describe(&self) -> SchemaDescription {
+        SchemaDescription {
+            mode: self.mode,
+            namespaces: self
+                .namespaces
+                .iter()
+                .map(|(name, owner)| NamespaceDescription {
+                    name: name.clone(),
+                    owner: owner.clone(),
+                    keys: self.keys.values().filter(|spec| spec.namespace() == name).cloned().collect(),
+                })
+                .collect(),
+        }
+    }
+}
+
+static GLOBAL_SCHEMA: Lazy<RwLock<MetadataSchema>> =
+    Lazy::new(|| RwLock::new(MetadataSchema::builtin(ValidationMode::default())));
+
+/// The process-wide schema that signal creation validates against
+pub fn global() -> &'static RwLock<MetadataSchema> {
+    &GLOBAL_SCHEMA
+}
+
+/// Deserialize signal metadata, mapping legacy keys from old records
+pub(crate) fn deserialize_metadata<'de, D>(deserializer: D) -> std::result::Result<HashMap<String, String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let mut metadata = HashMap::<String, String>::deserialize(deserializer)?;
+    global().read().migrate_legacy(&mut metadata);
+    Ok(metadata)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::NeuronSignal;
+
+    fn metadata(entries: &[(&str, &str)]) -> HashMap<String, String> {
+        entries.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn test_generated_constants_are_namespaced() {
+        assert_eq!(keys::trace::CHAIN_ID, "trace.chain_id");
+        assert_eq!(keys::experiment::VARIANT, "experiment.variant");
+        assert_eq!(keys::cost::MODEL, "cost.model");
+
+        let schema = MetadataSchema::builtin(ValidationMode::Strict);
+        assert_eq!(schema.get(keys::cost::TOKENS).unwrap().value_type, MetadataType::Integer);
+        assert_eq!(schema.get(keys::validation::STATUS).unwrap().owner, "validation");
+    }
+
+    #[test]
+    fn test_strict_mode_rejects_unknown_keys() {
+        let schema = MetadataSchema::builtin(ValidationMode::Strict);
+        assert!(schema.validate(&metadata(&[(keys::trace::ID, "abc"), (keys::cost::USD, "0.25")])).is_ok());
+
+        let err = schema.validate(&metadata(&[("billing.plan", "pro")])).unwrap_err();
+        assert!(err.to_string().contains("billing.plan"));
+        assert!(schema.validate(&metadata(&[("experiment.bucket", "a")])).is_err());
+        assert!(schema.validate(&metadata(&[("variant", "a")])).is_err());
+    }
+
+    #[test]
+    fn test_permissive_mode_accepts_unknown_keys() {
+        let schema = MetadataSchema::builtin(ValidationMode::Permissive);
+        let entries = metadata(&[("billing.plan", "pro"), ("experiment.bucket", "a"), ("variant", "a")]);
+        assert!(schema.validate(&entries).is_ok());
+        assert_eq!(schema.check(&entries).len(), 3);
+        assert!(matches!(
+            schema.check(&metadata(&[("experiment.bucket", "a")]))[0],
+            MetadataViolation::UnknownKey { .. }
+        ));
+    }
+
+    #[test]
+    fn test_type_violations_fail_in_every_mode() {
+        for mode in [ValidationMode::Strict, ValidationMode::Permissive] {
+            let schema = MetadataSchema::builtin(mode);
+            for (key, value) in [
+                (keys::cost::TOKENS, "many"),
+                (keys::cost::USD, "NaN"),
+                (keys::routing::IS_QUERY, "yes"),
+                (keys::network::HOP_COUNT, "1.5"),
+            ] {
+                let violations = schema.check(&metadata(&[(key, value)]));
+                assert!(violations[0].is_type_error(), "{} = {}", key, value);
+                assert!(schema.validate(&metadata(&[(key, value)])).is_err(), "{:?}: {} = {}", mode, key, value);
+            }
+            assert!(schema.validate(&metadata(&[(keys::cost::TOKENS, "-3"), (keys::routing::IS_QUERY, "true")])).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_registration_rejects_collisions() {
+        let mut schema = MetadataSchema::builtin(ValidationMode::Strict);
+        assert!(schema.register_namespace("trace", "someone_else").is_err());
+        assert!(schema.register_namespace("trace", "tracing").is_ok());
+
+        let spec = |key: &str, owner: &str| KeySpec {
+            key: key.to_string(),
+            value_type: MetadataType::Timestamp,
+            owner: owner.to_string(),
+            description: String::new(),
+            legacy_key: None,
+        };
+        assert!(schema.register(spec(keys::trace::ID, "tracing")).is_err());
+        assert!(schema.register(spec("trace.started_at", "cost_tracking")).is_err());
+        assert!(schema.register(spec("billing.started_at", "billing")).is_err());
+
+        schema.register_namespace("billing", "billing").unwrap();
+        schema.register(spec("billing.started_at", "billing")).unwrap();
+        assert!(schema.validate(&metadata(&[("billing.started_at", "2024-01-01T00:00:00Z")])).is_ok());
+        assert!(schema.validate(&metadata(&[("billing.started_at", "yesterday")])).is_err());
+        assert!(schema.describe().namespaces.iter().any(|ns| ns.name == "billing" && ns.keys.len() == 1));
+    }
+
+    #[test]
+    fn test_legacy_keys_map_to_namespaced_form() {
+        let schema = MetadataSchema::builtin(ValidationMode::Strict);
+        let mut entries = metadata(&[
+            ("chain_id", "c1"),
+            ("validation_status", "failed"),
+            ("hop_count", "2"),
+            (keys::auth::USER_ID, "new"),
+            ("user_id", "old"),
+        ]);
+        assert_eq!(schema.migrate_legacy(&mut entries), 4);
+        assert_eq!(entries[keys::trace::CHAIN_ID], "c1");
+        assert_eq!(entries[keys::validation::STATUS], "failed");
+        assert_eq!(entries[keys::network::HOP_COUNT], "2");
+        assert_eq!(entries[keys::auth::USER_ID], "new");
+        assert!(!entries.contains_key("chain_id") && !entries.contains_key("user_id"));
+        assert!(schema.validate(&entries).is_ok());
+    }
+
+    #[test]
+    fn test_old_signal_records_are_migrated_on_read() {
+        let mut json = serde_json::to_value(NeuronSignal::forward("a", "b", "L4", "L3", "task".to_string())).unwrap();
+        json["metadata"] = serde_json::json!({"chain_id": "c1", "is_query": "true", "custom": "kept"});
+        let signal: NeuronSignal = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(signal.metadata[keys::trace::CHAIN_ID], "c1");
+        assert_eq!(signal.metadata[keys::routing::IS_QUERY], "true");
+        assert_eq!(signal.metadata["custom"], "kept");
+
+        json.as_object_mut().unwrap().remove("metadata");
+        let signal: NeuronSignal = serde_json::from_value(json).unwrap();
+        assert!(signal.metadata.is_empty());
+    }
+}
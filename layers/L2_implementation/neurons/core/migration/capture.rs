@@ -0,0 +1,447 @@
+//! Sampled capture of mirrored traffic for migration verification
+//!
+//! While both systems handle a request (shadow mode), a configurable share of
+//! requests is captured: the redacted payload, both responses, the latency of
+//! each path and the routing decision. `hal9-migrate verify --captures`
+//! compares the two paths' distributions from these captures.
+//!
+//! Captures hold user payloads, so every text field passes through the
+//! [`PiiPipeline`] before it is stored, and the store is bounded by entry
+//! count, total size and age. [`CaptureStore::purge`] deletes in bulk.
+
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use parking_lot::RwLock;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{Error, Result};
+use super::router::{ProcessRequest, ProcessResponse, RoutingDecision};
+
+/// Attribute naming the layer that served a request
+pub const LAYER_ATTRIBUTE: &str = "layer";
+
+/// Replaces personal data in text with typed placeholders
+pub struct PiiPipeline {
+    rules: Vec<(Regex, &'static str)>,
+}
+
+impl Default for PiiPipeline {
+    fn default() -> Self {
+        // Ordered so that longer, more specific patterns win: a card number
+        // must not be half-eaten by the phone rule
+        let rules = [
+            (r"(?i)\b[A-Z0-9._%+-]+@[A-Z0-9.-]+\.[A-Z]{2,}\b", "[EMAIL]"),
+            (r"(?i)\bbearer\s+[A-Z0-9._~+/=-]+", "Bearer [TOKEN]"),
+            (r"\b(?:sk|pk|api|key)[-_][A-Za-z0-9_-]{16,}\b", "[API_KEY]"),
+            (r"\b(?:\d[ -]?){13,19}\b", "[CARD]"),
+            (r"\b\d{3}-\d{2}-\d{4}\b", "[SSN]"),
+            (r"\+?\b\d{1,3}[ .-]?\(?\d{3}\)?[ .-]?\d{3}[ .-]?\d{4}\b", "[PHONE]"),
+            (r"\b(?:\d{1,3}\.){3}\d{1,3}\b", "[IP]"),
+        ];
+        Self {
+            rules: rules
+                .into_iter()
+                .map(|(pattern, placeholder)| (Regex::new(pattern).expect("valid PII pattern"), placeholder))
+                .collect(),
+        }
+    }
+}
+
+impl PiiPipeline {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a rule applied after the built-in ones
+    pub fn with_rule(mut self, pattern: &str, placeholder: &'static str) -> Result<Self> {
+        let regex = Regex::new(pattern)
+            .map_err(|e| Error::Config(format!("Invalid PII pattern {}: {}", pattern, e)))?;
+        self.rules.push((regex, placeholder));
+        Ok(self)
+    }
+
+    pub fn redact(&self, text: &str) -> String {
+        self.rules.iter().fold(text.to_string(), |text, (regex, placeholder)| {
+            regex.replace_all(&text, *placeholder).into_owned()
+        })
+    }
+}
+
+/// Sampling rate and retention limits
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CaptureConfig {
+    /// Share of mirrored requests captured, 0.0 - 1.0
+    pub sample_rate: f64,
+    /// Captures kept; the oldest are evicted first
+    pub max_entries: usize,
+    /// Total size of kept captures, in bytes of redacted text
+    pub max_bytes: usize,
+    /// Captures older than this are dropped
+    pub ttl: Duration,
+    /// Longer payloads and responses are truncated before storing
+    pub max_field_bytes: usize,
+}
+
+impl Default for CaptureConfig {
+    fn default() -> Self {
+        Self {
+            sample_rate: 0.01,
+            max_entries: 10_000,
+            max_bytes: 64 * 1024 * 1024,
+            ttl: Duration::from_secs(24 * 60 * 60),
+            max_field_bytes: 16 * 1024,
+        }
+    }
+}
+
+/// How one system handled a captured request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PathSample {
+    pub latency_ms: f64,
+    /// Response length before truncation; 0 for errors
+    pub response_length: usize,
+    /// Redacted response, or `None` for errors
+    pub response: Option<String>,
+    /// Error variant when the path failed
+    pub error_class: Option<String>,
+}
+
+/// One captured mirrored request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrafficCapture {
+    pub id: Uuid,
+    pub request_id: Uuid,
+    pub captured_at: DateTime<Utc>,
+    pub path: String,
+    pub layer: String,
+    /// `flat`, `hierarchical`, or `both:<primary>`
+    pub decision: String,
+    /// Redacted request content
+    pub payload: String,
+    pub flat: PathSample,
+    pub hierarchical: PathSample,
+}
+
+impl TrafficCapture {
+    fn size(&self) -> usize {
+        self.payload.len()
+            + self.flat.response.as_ref().map_or(0, String::len)
+            + self.hierarchical.response.as_ref().map_or(0, String::len)
+    }
+}
+
+/// The name of an error's variant, e.g. `Timeout`
+pub fn error_class(error: &Error) -> String {
+    let debug = format!("{:?}", error);
+    debug
+        .split(|c: char| !c.is_alphanumeric() && c != '_')
+        .next()
+        .unwrap_or("Unknown")
+        .to_string()
+}
+
+/// `flat`, `hierarchical`, or `both:<primary>`
+pub fn decision_label(decision: &RoutingDecision) -> String {
+    match decision {
+        RoutingDecision::Flat => "flat".to_string(),
+        RoutingDecision::Hierarchical => "hierarchical".to_string(),
+        RoutingDecision::Both { primary } => format!("both:{}", decision_label(primary)),
+    }
+}
+
+/// Bounded, redacting store of sampled captures
+pub struct CaptureStore {
+    config: CaptureConfig,
+    pii: PiiPipeline,
+    entries: RwLock<VecDeque<TrafficCapture>>,
+    bytes: RwLock<usize>,
+}
+
+impl CaptureStore {
+    pub fn new(config: CaptureConfig) -> Result<Self> {
+        if !(0.0..=1.0).contains(&config.sample_rate) {
+            return Err(Error::Config(format!(
+                "Capture sample rate must be between 0 and 1, got {}",
+                config.sample_rate
+            )));
+        }
+        Ok(Self {
+            config,
+            pii: PiiPipeline::default(),
+            entries: RwLock::new(VecDeque::new()),
+            bytes: RwLock::new(0),
+        })
+    }
+
+    /// Use a custom redaction pipeline
+    pub fn with_pii_pipeline(mut self, pii: PiiPipeline) -> Self {
+        self.pii = pii;
+        self
+    }
+
+    pub fn config(&self) -> &CaptureConfig {
+        &self.config
+    }
+
+    /// Whether a request falls in the sample. Decided by request id, so a
+    /// request retried with the same id is sampled consistently.
+    pub fn should_sample(&self, request_id: Uuid) -> bool {
+        // Low bytes first: the version and variant bits of a v4 id then only
+        // touch the least significant bits
+        let bucket = u64::from_le_bytes(request_id.as_bytes()[8..].try_into().expect("8 bytes"));
+        (bucket as f64 / u64::MAX as f64) < self.config.sample_rate
+    }
+
+    /// Capture a mirrored request if it falls in the sample; returns whether
+    /// it was stored
+    pub fn record(
+        &self,
+        request: &ProcessRequest,
+        decision: &RoutingDecision,
+        flat: (&Result<ProcessResponse>, Duration),
+        hierarchical: (&Result<ProcessResponse>, Duration),
+    ) -> bool {
+        if !self.should_sample(request.id) {
+            return false;
+        }
+
+        let capture = TrafficCapture {
+            id: Uuid::new_v4(),
+            request_id: request.id,
+            captured_at: Utc::now(),
+            path: self.pii.redact(&request.path),
+            layer: request.attributes.get(LAYER_ATTRIBUTE).cloned().unwrap_or_else(|| "unknown".to_string()),
+            decision: decision_label(decision),
+            payload: self.clip(&request.content),
+            flat: self.sample(flat.0, flat.1),
+            hierarchical: self.sample(hierarchical.0, hierarchical.1),
+        };
+        self.insert(capture);
+        true
+    }
+
+    fn sample(&self, result: &Result<ProcessResponse>, latency: Duration) -> PathSample {
+        let latency_ms = latency.as_secs_f64() * 1000.0;
+        match result {
+            Ok(response) => PathSample {
+                latency_ms,
+                response_length: response.content.len(),
+                response: Some(self.clip(&response.content)),
+                error_class: None,
+            },
+            Err(e) => PathSample {
+                latency_ms,
+                response_length: 0,
+                response: None,
+                error_class: Some(error_class(e)),
+            },
+        }
+    }
+
+    /// Redact, then truncate on a character boundary
+    fn clip(&self, text: &str) -> String {
+        let mut redacted = self.pii.redact(text);
+        if redacted.len() > self.config.max_field_bytes {
+            let mut end = self.config.max_field_bytes;
+            while !redacted.is_char_boundary(end) {
+                end -= 1;
+            }
+            redacted.truncate(end);
+        }
+        redacted
+    }
+
+    fn insert(&self, capture: TrafficCapture) {
+        let mut entries = self.entries.write();
+        let mut bytes = self.bytes.write();
+        *bytes += capture.size();
+        entries.push_back(capture);
+
+        let cutoff = self.cutoff();
+        while let Some(oldest) = entries.front() {
+            let over = entries.len() > self.config.max_entries || *bytes > self.config.max_bytes;
+            if !over && oldest.captured_at >= cutoff {
+                break;
+            }
+            *bytes -= oldest.size();
+            entries.pop_front();
+        }
+    }
+
+    fn cutoff(&self) -> DateTime<Utc> {
+        chrono::Duration::from_std(self.config.ttl)
+            .ok()
+            .and_then(|ttl| Utc::now().checked_sub_signed(ttl))
+            .unwrap_or(DateTime::<Utc>::MIN_UTC)
+    }
+
+    /// Unexpired captures, oldest first
+    pub fn list(&self) -> Vec<TrafficCapture> {
+        let cutoff = self.cutoff();
+        self.entries.read().iter()
+            .filter(|capture| capture.captured_at >= cutoff)
+            .cloned()
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.read().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Total size of stored captures
+    pub fn bytes(&self) -> usize {
+        *self.bytes.read()
+    }
+
+    /// Delete every capture taken before `before`, or all of them; returns
+    /// how many were deleted
+    pub fn purge(&self, before: Option<DateTime<Utc>>) -> usize {
+        let mut entries = self.entries.write();
+        let mut bytes = self.bytes.write();
+        let count = entries.len();
+        match before {
+            Some(before) => entries.retain(|capture| capture.captured_at >= before),
+            None => entries.clear(),
+        }
+        *bytes = entries.iter().map(TrafficCapture::size).sum();
+        count - entries.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn request(content: &str) -> ProcessRequest {
+        ProcessRequest {
+            id: Uuid::new_v4(),
+            user_id: None,
+            path: "/process".to_string(),
+            headers: HashMap::new(),
+            query_params: HashMap::new(),
+            attributes: HashMap::from([(LAYER_ATTRIBUTE.to_string(), "L3".to_string())]),
+            content: content.to_string(),
+        }
+    }
+
+    fn response(content: &str) -> Result<ProcessResponse> {
+        Ok(ProcessResponse {
+            content: content.to_string(),
+            confidence: 0.9,
+            metadata: serde_json::Value::Null,
+            processing_time_ms: 10,
+        })
+    }
+
+    fn shadow() -> RoutingDecision {
+        RoutingDecision::Both { primary: Box::new(RoutingDecision::Flat) }
+    }
+
+    fn capture_store(config: CaptureConfig) -> CaptureStore {
+        CaptureStore::new(config).unwrap()
+    }
+
+    #[test]
+    fn test_sampling_rate_accuracy() {
+        for rate in [0.01, 0.1, 0.5] {
+            let store = capture_store(CaptureConfig { sample_rate: rate, max_entries: 100_000, ..Default::default() });
+            let total = 20_000;
+            let ok = response("ok");
+            let captured = (0..total)
+                .filter(|_| store.record(&request("hi"), &shadow(), (&ok, Duration::ZERO), (&ok, Duration::ZERO)))
+                .count();
+
+            let observed = captured as f64 / total as f64;
+            // Within five standard deviations of a binomial sample
+            let tolerance = 5.0 * (rate * (1.0 - rate) / total as f64).sqrt();
+            assert!((observed - rate).abs() < tolerance, "rate {} observed {}", rate, observed);
+            assert_eq!(store.len(), captured);
+        }
+
+        let none = capture_store(CaptureConfig { sample_rate: 0.0, ..Default::default() });
+        let all = capture_store(CaptureConfig { sample_rate: 1.0, ..Default::default() });
+        for _ in 0..1_000 {
+            let id = Uuid::new_v4();
+            assert!(!none.should_sample(id));
+            assert!(all.should_sample(id));
+        }
+        assert!(CaptureStore::new(CaptureConfig { sample_rate: 1.5, ..Default::default() }).is_err());
+    }
+
+    #[test]
+    fn test_sampling_is_stable_per_request() {
+        let store = capture_store(CaptureConfig { sample_rate: 0.5, ..Default::default() });
+        for _ in 0..100 {
+            let id = Uuid::new_v4();
+            assert_eq!(store.should_sample(id), store.should_sample(id));
+        }
+    }
+
+    #[test]
+    fn test_redaction_applied_to_payload_and_responses() {
+        let store = capture_store(CaptureConfig { sample_rate: 1.0, ..Default::default() });
+        let request = request(
+            "Email jane.doe@example.com or call +1 415-555-0100, card 4111 1111 1111 1111, \
+             auth Bearer abc.def.ghi from 10.0.0.12, key sk-live_0123456789abcdefXYZ",
+        );
+        let flat = response("Sent to jane.doe@example.com");
+        let hierarchical: Result<ProcessResponse> = Err(Error::Timeout(5));
+        assert!(store.record(&request, &shadow(), (&flat, Duration::from_millis(12)), (&hierarchical, Duration::from_millis(40))));
+
+        let capture = &store.list()[0];
+        for secret in ["jane.doe", "415-555", "4111", "abc.def", "10.0.0.12", "sk-live"] {
+            assert!(!capture.payload.contains(secret), "{} leaked into {}", secret, capture.payload);
+        }
+        for placeholder in ["[EMAIL]", "[PHONE]", "[CARD]", "Bearer [TOKEN]", "[IP]", "[API_KEY]"] {
+            assert!(capture.payload.contains(placeholder), "{} missing from {}", placeholder, capture.payload);
+        }
+        assert_eq!(capture.flat.response.as_deref(), Some("Sent to [EMAIL]"));
+        assert_eq!(capture.flat.response_length, "Sent to jane.doe@example.com".len());
+        assert_eq!(capture.hierarchical.error_class.as_deref(), Some("Timeout"));
+        assert_eq!(capture.layer, "L3");
+        assert_eq!(capture.decision, "both:flat");
+        assert!((capture.hierarchical.latency_ms - 40.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_store_limits_and_purge() {
+        let store = capture_store(CaptureConfig {
+            sample_rate: 1.0,
+            max_entries: 5,
+            max_bytes: 2_000,
+            max_field_bytes: 100,
+            ..Default::default()
+        });
+        let ok = response(&"x".repeat(500));
+        for i in 0..20 {
+            store.record(&request(&format!("request {}", i)), &shadow(), (&ok, Duration::ZERO), (&ok, Duration::ZERO));
+        }
+        assert_eq!(store.len(), 5);
+        assert!(store.bytes() <= 2_000);
+        assert!(store.list().iter().all(|c| c.flat.response.as_ref().unwrap().len() == 100));
+        assert_eq!(store.list().last().unwrap().payload, "request 19");
+
+        let expiring = capture_store(CaptureConfig { sample_rate: 1.0, ttl: Duration::ZERO, ..Default::default() });
+        expiring.record(&request("old"), &shadow(), (&ok, Duration::ZERO), (&ok, Duration::ZERO));
+        std::thread::sleep(Duration::from_millis(2));
+        assert!(expiring.list().is_empty());
+
+        let cutoff = Utc::now();
+        std::thread::sleep(Duration::from_millis(2));
+        store.record(&request("new"), &shadow(), (&ok, Duration::ZERO), (&ok, Duration::ZERO));
+        assert_eq!(store.purge(Some(cutoff)), 4);
+        assert_eq!(store.len(), 1);
+        assert_eq!(store.purge(None), 1);
+        assert!(store.is_empty());
+        assert_eq!(store.bytes(), 0);
+    }
+}
@@ -6,18 +6,21 @@
 //! - State migration engine
 //! - Rollback capabilities
 //! - Monitoring and observability
+//! - Sampled capture of mirrored traffic for verification
 
 pub mod feature_flags;
 pub mod router;
 pub mod state_migration;
 pub mod rollback;
 pub mod monitoring;
+pub mod capture;
 
 pub use feature_flags::{FeatureFlags, FeatureFlagManager, RequestContext};
 pub use router::{MigrationRouter, RoutingDecision};
 pub use state_migration::{StateMigrationEngine, MigrationProgress};
 pub use rollback::{RollbackManager, RollbackStrategy};
 pub use monitoring::{MigrationMonitor, MigrationMetrics};
+pub use capture::{CaptureConfig, CaptureStore, PiiPipeline, TrafficCapture};
 
 use crate::{Result, Error};
 use std::sync::Arc;
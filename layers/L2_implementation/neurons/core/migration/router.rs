@@ -4,6 +4,7 @@ use std::sync::Arc;
 use parking_lot::RwLock;
 use uuid::Uuid;
 use crate::Result;
+use super::capture::CaptureStore;
 use super::feature_flags::{FeatureFlagManager, RequestContext};
 
 /// Decision on which system should handle a request
@@ -118,6 +119,7 @@ pub struct RequestRouter {
     flat_handler: Arc<dyn RequestHandler>,
     hierarchical_handler: Arc<dyn RequestHandler>,
     router: Arc<MigrationRouter>,
+    captures: Option<Arc<CaptureStore>>,
 }
 
 impl RequestRouter {
@@ -130,9 +132,16 @@ impl RequestRouter {
             flat_handler,
             hierarchical_handler,
             router,
+            captures: None,
         }
     }
     
+    /// Capture a sample of mirrored requests into `captures`
+    pub fn with_captures(mut self, captures: Arc<CaptureStore>) -> Self {
+        self.captures = Some(captures);
+        self
+    }
+    
     /// Process a request according to routing decision
     pub async fn process(&self, request: ProcessRequest) -> Result<ProcessResponse> {
         let context = RequestContext {
@@ -155,10 +164,15 @@ impl RequestRouter {
             }
             RoutingDecision::Both { primary } => {
                 // Process in both systems
-                let flat_future = self.flat_handler.handle(request.clone());
-                let hier_future = self.hierarchical_handler.handle(request.clone());
+                let flat_future = timed(self.flat_handler.handle(request.clone()));
+                let hier_future = timed(self.hierarchical_handler.handle(request.clone()));
+                
+                let ((flat_result, flat_latency), (hier_result, hier_latency)) = tokio::join!(flat_future, hier_future);
                 
-                let (flat_result, hier_result) = tokio::join!(flat_future, hier_future);
+                if let Some(captures) = &self.captures {
+                    let decision = RoutingDecision::Both { primary: primary.clone() };
+                    captures.record(&request, &decision, (&flat_result, flat_latency), (&hier_result, hier_latency));
+                }
                 
                 // Compare results
                 if let (Ok(flat_response), Ok(hier_response)) = (&flat_result, &hier_result) {
@@ -203,6 +217,13 @@ impl RequestRouter {
     }
 }
 
+/// Await `future`, measuring how long it took
+async fn timed<T>(future: impl std::future::Future<Output = T>) -> (T, std::time::Duration) {
+    let started = std::time::Instant::now();
+    let output = future.await;
+    (output, started.elapsed())
+}
+
 /// Trait for request handlers
 #[async_trait::async_trait]
 pub trait RequestHandler: Send + Sync {
@@ -0,0 +1,378 @@
+//! Verifiable chain receipts
+//!
+//! A receipt commits to every signal a chain processed: each signal becomes a
+//! [`ReceiptLeaf`] (hashes of its content alongside model, tokens and
+//! timestamp), the leaves form a Merkle tree, and the server signs the root
+//! with its Ed25519 key. Anyone holding a receipt, a signal's leaf and its
+//! [`InclusionProof`] can check the signal was part of the chain with
+//! [`ChainReceipt::verify_inclusion`], without asking the server.
+//!
+//! Leaves and inner nodes are hashed with distinct prefixes so a leaf can
+//! never be passed off as an inner node. An unpaired node at the end of a
+//! level is carried up unchanged.
+
+use chrono::{DateTime, SubsecRound, Utc};
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{Error, Result};
+
+const LEAF_PREFIX: u8 = 0x00;
+const NODE_PREFIX: u8 = 0x01;
+const SIGNATURE_CONTEXT: &[u8] = b"hal9-receipt-v1";
+
+type Hash = [u8; 32];
+
+/// The committed record of one processed signal
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReceiptLeaf {
+    pub signal_id: String,
+    pub neuron_id: String,
+    pub layer: String,
+    /// Hex SHA-256 of the signal's output, or of its error if it failed
+    pub content_sha256: String,
+    pub model: Option<String>,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub timestamp_ms: i64,
+}
+
+impl ReceiptLeaf {
+    /// Hex SHA-256 of a signal's content, as stored in `content_sha256`
+    pub fn content_hash(content: &str) -> String {
+        hex::encode(Sha256::digest(content.as_bytes()))
+    }
+
+    /// Leaf hash: every field length-prefixed, so no two leaves encode alike
+    pub fn hash(&self) -> Hash {
+        let mut hasher = Sha256::new();
+        hasher.update([LEAF_PREFIX]);
+        for field in [&self.signal_id, &self.neuron_id, &self.layer, &self.content_sha256] {
+            hash_str(&mut hasher, field);
+        }
+        match &self.model {
+            Some(model) => {
+                hasher.update([1]);
+                hash_str(&mut hasher, model);
+            }
+            None => hasher.update([0]),
+        }
+        hasher.update(self.prompt_tokens.to_be_bytes());
+        hasher.update(self.completion_tokens.to_be_bytes());
+        hasher.update(self.timestamp_ms.to_be_bytes());
+        hasher.finalize().into()
+    }
+}
+
+fn hash_str(hasher: &mut Sha256, value: &str) {
+    hasher.update((value.len() as u64).to_be_bytes());
+    hasher.update(value.as_bytes());
+}
+
+fn hash_node(left: &Hash, right: &Hash) -> Hash {
+    let mut hasher = Sha256::new();
+    hasher.update([NODE_PREFIX]);
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize().into()
+}
+
+/// Which side of the running hash a sibling sits on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Side {
+    Left,
+    Right,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProofStep {
+    pub side: Side,
+    /// Hex sibling hash
+    pub hash: String,
+}
+
+/// Sibling hashes from a leaf up to the Merkle root
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InclusionProof {
+    pub leaf_index: usize,
+    pub path: Vec<ProofStep>,
+}
+
+impl InclusionProof {
+    /// Root reached by hashing `leaf` up the path
+    pub fn root_for(&self, leaf: &ReceiptLeaf) -> Result<String> {
+        let mut hash = leaf.hash();
+        for step in &self.path {
+            let sibling = decode_hash(&step.hash)?;
+            hash = match step.side {
+                Side::Left => hash_node(&sibling, &hash),
+                Side::Right => hash_node(&hash, &sibling),
+            };
+        }
+        Ok(hex::encode(hash))
+    }
+}
+
+/// Merkle tree over a chain's leaves
+#[derive(Debug, Clone)]
+pub struct MerkleTree {
+    /// Leaf hashes first, the root last
+    levels: Vec<Vec<Hash>>,
+}
+
+impl MerkleTree {
+    pub fn new(leaves: &[ReceiptLeaf]) -> Self {
+        let mut levels = vec![leaves.iter().map(ReceiptLeaf::hash).collect::<Vec<_>>()];
+        while levels.last().is_some_and(|level| level.len() > 1) {
+            let level = levels.last().unwrap();
+            let next = level
+                .chunks(2)
+                .map(|pair| match pair {
+                    [left, right] => hash_node(left, right),
+                    [single] => *single,
+                    _ => unreachable!(),
+                })
+                .collect();
+            levels.push(next);
+        }
+        Self { levels }
+    }
+
+    pub fn len(&self) -> usize {
+        self.levels[0].len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Hex root; a tree without leaves has the hash of no input as its root
+    pub fn root(&self) -> String {
+        match self.levels.last().and_then(|level| level.first()) {
+            Some(root) => hex::encode(root),
+            None => hex::encode(Sha256::digest([])),
+        }
+    }
+
+    /// Proof for the leaf at `index`, if there is one
+    pub fn proof(&self, index: usize) -> Option<InclusionProof> {
+        if index >= self.len() {
+            return None;
+        }
+        let mut path = Vec::new();
+        let mut position = index;
+        for level in &self.levels[..self.levels.len() - 1] {
+            let sibling = position ^ 1;
+            if let Some(hash) = level.get(sibling) {
+                path.push(ProofStep {
+                    side: if position.is_multiple_of(2) { Side::Right } else { Side::Left },
+                    hash: hex::encode(hash),
+                });
+            }
+            position /= 2;
+        }
+        Some(InclusionProof { leaf_index: index, path })
+    }
+}
+
+/// Where a receipt's root was published outside this server
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AnchorRecord {
+    /// Anchor that published the root, e.g. "ethereum"
+    pub anchor: String,
+    /// Reference to the published record, e.g. a transaction hash
+    pub reference: String,
+    pub anchored_at: DateTime<Utc>,
+}
+
+/// Signed commitment to the signals of a finished chain
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChainReceipt {
+    pub chain_id: String,
+    /// Hex Merkle root over the chain's leaves
+    pub merkle_root: String,
+    pub leaf_count: usize,
+    pub issued_at: DateTime<Utc>,
+    /// Hex Ed25519 key of the issuing server
+    pub public_key: String,
+    /// Hex signature over chain ID, root, leaf count and issue time
+    pub signature: String,
+    /// Not covered by the signature; the anchor itself vouches for the root
+    pub anchor: Option<AnchorRecord>,
+}
+
+impl ChainReceipt {
+    fn signed_message(chain_id: &str, merkle_root: &str, leaf_count: usize, issued_at: DateTime<Utc>) -> Vec<u8> {
+        let mut message = SIGNATURE_CONTEXT.to_vec();
+        for part in [chain_id, merkle_root, &leaf_count.to_string(), &issued_at.timestamp().to_string()] {
+            message.push(b'\n');
+            message.extend_from_slice(part.as_bytes());
+        }
+        message
+    }
+
+    /// Raw Merkle root, for anchors that publish bytes
+    pub fn root_bytes(&self) -> Result<Vec<u8>> {
+        Ok(decode_hash(&self.merkle_root)?.to_vec())
+    }
+
+    /// Check the receipt was signed by `trusted_key` (hex). The embedded
+    /// `public_key` must match it: a receipt is only as trustworthy as the
+    /// key the verifier expects.
+    pub fn verify(&self, trusted_key: &str) -> Result<()> {
+        if !self.public_key.eq_ignore_ascii_case(trusted_key) {
+            return Err(Error::InvalidInput("Receipt was signed by an untrusted key".to_string()));
+        }
+        let key_bytes: [u8; 32] = decode_hash(trusted_key)?;
+        let key = VerifyingKey::from_bytes(&key_bytes)
+            .map_err(|e| Error::InvalidInput(format!("Invalid public key: {}", e)))?;
+        let signature_bytes: [u8; 64] = hex::decode(&self.signature)
+            .ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(|| Error::InvalidInput("Invalid signature encoding".to_string()))?;
+        let message = Self::signed_message(&self.chain_id, &self.merkle_root, self.leaf_count, self.issued_at);
+        key.verify(&message, &Signature::from_bytes(&signature_bytes))
+            .map_err(|_| Error::InvalidInput("Receipt signature does not match".to_string()))
+    }
+
+    /// Check `leaf` belongs to this receipt's chain: the receipt must be
+    /// signed by `trusted_key` and `proof` must lead from the leaf to its root
+    pub fn verify_inclusion(&self, trusted_key: &str, leaf: &ReceiptLeaf, proof: &InclusionProof) -> Result<()> {
+        self.verify(trusted_key)?;
+        if proof.leaf_index >= self.leaf_count {
+            return Err(Error::InvalidInput(format!(
+                "Leaf index {} is outside the receipt's {} leaves",
+                proof.leaf_index, self.leaf_count
+            )));
+        }
+        if proof.root_for(leaf)? != self.merkle_root {
+            return Err(Error::InvalidInput(format!(
+                "Signal {} is not included in chain {}",
+                leaf.signal_id, self.chain_id
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Server key that signs receipts
+pub struct ReceiptSigner {
+    key: SigningKey,
+}
+
+impl ReceiptSigner {
+    /// A fresh random key
+    pub fn generate() -> Self {
+        Self {
+            key: SigningKey::generate(&mut rand::rngs::OsRng),
+        }
+    }
+
+    /// Key from a hex-encoded 32-byte seed
+    pub fn from_hex(seed: &str) -> Result<Self> {
+        let seed = decode_hash(seed.trim())
+            .map_err(|_| Error::Config("Receipt signing key must be 32 hex-encoded bytes".to_string()))?;
+        Ok(Self {
+            key: SigningKey::from_bytes(&seed),
+        })
+    }
+
+    /// Hex public key receipts are verified against
+    pub fn public_key(&self) -> String {
+        hex::encode(self.key.verifying_key().as_bytes())
+    }
+
+    /// Sign a receipt for `tree`, issued now
+    pub fn sign(&self, chain_id: &str, tree: &MerkleTree) -> ChainReceipt {
+        let merkle_root = tree.root();
+        let issued_at = Utc::now().trunc_subsecs(0);
+        let message = ChainReceipt::signed_message(chain_id, &merkle_root, tree.len(), issued_at);
+        ChainReceipt {
+            chain_id: chain_id.to_string(),
+            merkle_root,
+            leaf_count: tree.len(),
+            issued_at,
+            public_key: self.public_key(),
+            signature: hex::encode(self.key.sign(&message).to_bytes()),
+            anchor: None,
+        }
+    }
+}
+
+fn decode_hash(value: &str) -> Result<Hash> {
+    hex::decode(value)
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| Error::InvalidInput(format!("Invalid hash '{}'", value)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn leaf(i: usize) -> ReceiptLeaf {
+        ReceiptLeaf {
+            signal_id: format!("signal-{}", i),
+            neuron_id: "neuron-l2".to_string(),
+            layer: "L2".to_string(),
+            content_sha256: ReceiptLeaf::content_hash(&format!("output {}", i)),
+            model: Some("claude-3-haiku".to_string()),
+            prompt_tokens: 100 + i as u64,
+            completion_tokens: 50,
+            timestamp_ms: 1_700_000_000_000 + i as i64,
+        }
+    }
+
+    #[test]
+    fn test_every_leaf_proves_inclusion() {
+        let signer = ReceiptSigner::generate();
+        for count in 1..=9 {
+            let leaves: Vec<_> = (0..count).map(leaf).collect();
+            let tree = MerkleTree::new(&leaves);
+            let receipt = signer.sign("chain-1", &tree);
+            for (index, leaf) in leaves.iter().enumerate() {
+                let proof = tree.proof(index).unwrap();
+                assert!(receipt.verify_inclusion(&signer.public_key(), leaf, &proof).is_ok(), "{} of {}", index, count);
+            }
+            assert!(tree.proof(count).is_none());
+        }
+    }
+
+    #[test]
+    fn test_tampered_leaf_fails() {
+        let signer = ReceiptSigner::generate();
+        let leaves: Vec<_> = (0..5).map(leaf).collect();
+        let tree = MerkleTree::new(&leaves);
+        let receipt = signer.sign("chain-1", &tree);
+        let proof = tree.proof(2).unwrap();
+
+        let mut tampered = leaves[2].clone();
+        tampered.content_sha256 = ReceiptLeaf::content_hash("something else");
+        assert!(receipt.verify_inclusion(&signer.public_key(), &tampered, &proof).is_err());
+
+        let mut tampered = leaves[2].clone();
+        tampered.completion_tokens += 1;
+        assert!(receipt.verify_inclusion(&signer.public_key(), &tampered, &proof).is_err());
+
+        // A leaf from one position does not verify with another's proof
+        assert!(receipt.verify_inclusion(&signer.public_key(), &leaves[3], &proof).is_err());
+    }
+
+    #[test]
+    fn test_signature_binds_root_and_key() {
+        let signer = ReceiptSigner::from_hex(&"11".repeat(32)).unwrap();
+        let tree = MerkleTree::new(&[leaf(0), leaf(1)]);
+        let receipt = signer.sign("chain-1", &tree);
+        assert!(receipt.verify(&signer.public_key()).is_ok());
+
+        let mut forged = receipt.clone();
+        forged.merkle_root = MerkleTree::new(&[leaf(0)]).root();
+        assert!(forged.verify(&signer.public_key()).is_err());
+
+        let other = ReceiptSigner::generate();
+        assert!(receipt.verify(&other.public_key()).is_err());
+        assert!(ReceiptSigner::from_hex("not hex").is_err());
+    }
+}
@@ -0,0 +1,406 @@
+//! Secrets in configuration
+//!
+//! Secret config values are held in [`SecretString`], which never shows its
+//! value through Debug, Display or Serialize. In config files a secret may be
+//! written as `enc:v1:<base64>`: AES-256-GCM under a master key, with the
+//! random nonce prepended to the ciphertext. Encrypted values are decrypted
+//! once at load time by [`crate::ServerConfig::decrypt_secrets`].
+
+use aes_gcm::aead::{Aead, AeadCore, OsRng};
+use aes_gcm::{Aes256Gcm, Key, KeyInit, Nonce};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::path::PathBuf;
+
+use crate::{Error, Result};
+
+/// Prefix of encrypted config values
+pub const ENCRYPTED_PREFIX: &str = "enc:";
+
+/// Current encryption format version
+pub const ENCRYPTION_VERSION: &str = "v1";
+
+/// Environment variable holding the base64 master key
+pub const MASTER_KEY_ENV: &str = "HAL9_MASTER_KEY";
+
+/// Environment variable naming a file that holds the base64 master key
+pub const MASTER_KEY_FILE_ENV: &str = "HAL9_MASTER_KEY_FILE";
+
+/// Config paths of every secret field, for tools editing raw config files
+pub const SECRET_FIELDS: &[&[&str]] = &[
+    &["claude", "api_key"],
+    &["auth", "jwt_secret"],
+    &["receipts", "signing_key"],
+    &["receipts", "anchor", "private_key"],
+    &["memory", "tiering", "backend", "secret_access_key"],
+    &["shadow", "api_key"],
+    &["billing_export", "destination", "secret_access_key"],
+    &["billing_export", "destination", "secret"],
+];
+
+const REDACTED: &str = "[REDACTED]";
+const NONCE_LEN: usize = 12;
+const KEY_LEN: usize = 32;
+
+/// String that is redacted wherever it might be logged or serialized
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct SecretString(String);
+
+impl SecretString {
+    pub fn new(value: impl Into<String>) -> Self {
+        Self(value.into())
+    }
+
+    /// The secret value itself
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+
+    /// Whether the value is still in `enc:` form
+    pub fn is_encrypted(&self) -> bool {
+        self.0.starts_with(ENCRYPTED_PREFIX)
+    }
+}
+
+impl From<String> for SecretString {
+    fn from(value: String) -> Self {
+        Self(value)
+    }
+}
+
+impl From<&str> for SecretString {
+    fn from(value: &str) -> Self {
+        Self(value.to_string())
+    }
+}
+
+impl fmt::Debug for SecretString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(REDACTED)
+    }
+}
+
+impl fmt::Display for SecretString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(REDACTED)
+    }
+}
+
+impl Serialize for SecretString {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_str(REDACTED)
+    }
+}
+
+impl<'de> Deserialize<'de> for SecretString {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        String::deserialize(deserializer).map(Self)
+    }
+}
+
+/// 256-bit master key for config secrets
+#[derive(Clone, PartialEq, Eq)]
+pub struct MasterKey([u8; KEY_LEN]);
+
+impl MasterKey {
+    /// Fresh random key
+    pub fn generate() -> Self {
+        Self(Aes256Gcm::generate_key(OsRng).into())
+    }
+
+    /// Parse a base64 encoded 32-byte key
+    pub fn from_base64(encoded: &str) -> Result<Self> {
+        let bytes = BASE64.decode(encoded.trim())
+            .map_err(|e| Error::Config(format!("Master key is not valid base64: {}", e)))?;
+        let key: [u8; KEY_LEN] = bytes.try_into()
+            .map_err(|bytes: Vec<u8>| Error::Config(format!(
+                "Master key must be {} bytes, got {}",
+                KEY_LEN,
+                bytes.len()
+            )))?;
+        Ok(Self(key))
+    }
+
+    pub fn to_base64(&self) -> String {
+        BASE64.encode(self.0)
+    }
+}
+
+impl fmt::Debug for MasterKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(REDACTED)
+    }
+}
+
+/// Source of the master key: environment, a key file, or an external KMS
+pub trait MasterKeyProvider: Send + Sync {
+    fn master_key(&self) -> Result<MasterKey>;
+}
+
+/// Key from `HAL9_MASTER_KEY`, or else from the file named by `HAL9_MASTER_KEY_FILE`
+#[derive(Debug, Clone, Default)]
+pub struct EnvKeyProvider;
+
+impl MasterKeyProvider for EnvKeyProvider {
+    fn master_key(&self) -> Result<MasterKey> {
+        if let Ok(key) = std::env::var(MASTER_KEY_ENV) {
+            return MasterKey::from_base64(&key);
+        }
+        match std::env::var(MASTER_KEY_FILE_ENV) {
+            Ok(path) => FileKeyProvider::new(path).master_key(),
+            Err(_) => Err(Error::Config(format!(
+                "No master key: set {} or {}",
+                MASTER_KEY_ENV, MASTER_KEY_FILE_ENV
+            ))),
+        }
+    }
+}
+
+/// Key read from a file holding it in base64
+#[derive(Debug, Clone)]
+pub struct FileKeyProvider {
+    path: PathBuf,
+}
+
+impl FileKeyProvider {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+impl MasterKeyProvider for FileKeyProvider {
+    fn master_key(&self) -> Result<MasterKey> {
+        let contents = std::fs::read_to_string(&self.path)
+            .map_err(|e| Error::Config(format!("Failed to read master key file {}: {}", self.path.display(), e)))?;
+        MasterKey::from_base64(&contents)
+    }
+}
+
+/// Fixed key, for tests and embedding
+#[derive(Debug, Clone)]
+pub struct StaticKeyProvider(pub MasterKey);
+
+impl MasterKeyProvider for StaticKeyProvider {
+    fn master_key(&self) -> Result<MasterKey> {
+        Ok(self.0.clone())
+    }
+}
+
+/// Encrypts and decrypts `enc:v1:` config values under one master key
+pub struct SecretsCipher {
+    cipher: Aes256Gcm,
+}
+
+impl SecretsCipher {
+    pub fn new(key: &MasterKey) -> Self {
+        Self {
+            cipher: Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key.0)),
+        }
+    }
+
+    pub fn from_provider(provider: &dyn MasterKeyProvider) -> Result<Self> {
+        Ok(Self::new(&provider.master_key()?))
+    }
+
+    /// Encrypt a plaintext value into `enc:v1:<base64>` form
+    pub fn encrypt(&self, plaintext: &str) -> Result<String> {
+        let nonce = Aes256Gcm::generate_nonce(OsRng);
+        let ciphertext = self.cipher.encrypt(&nonce, plaintext.as_bytes())
+            .map_err(|_| Error::Config("Failed to encrypt secret".to_string()))?;
+
+        let mut payload = nonce.to_vec();
+        payload.extend_from_slice(&ciphertext);
+        Ok(format!("{}{}:{}", ENCRYPTED_PREFIX, ENCRYPTION_VERSION, BASE64.encode(payload)))
+    }
+
+    /// Decrypt an `enc:` value; fails on a wrong key or tampered value
+    pub fn decrypt(&self, value: &str) -> Result<String> {
+        let rest = value.strip_prefix(ENCRYPTED_PREFIX)
+            .ok_or_else(|| Error::Config("Secret is not encrypted".to_string()))?;
+        let (version, encoded) = rest.split_once(':')
+            .ok_or_else(|| Error::Config("Malformed encrypted secret".to_string()))?;
+        if version != ENCRYPTION_VERSION {
+            return Err(Error::Config(format!("Unsupported secret encryption version '{}'", version)));
+        }
+
+        let payload = BASE64.decode(encoded)
+            .map_err(|_| Error::Config("Malformed encrypted secret".to_string()))?;
+        if payload.len() <= NONCE_LEN {
+            return Err(Error::Config("Malformed encrypted secret".to_string()));
+        }
+        let (nonce, ciphertext) = payload.split_at(NONCE_LEN);
+
+        let plaintext = self.cipher.decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| Error::Config("Failed to decrypt secret: wrong master key or corrupted value".to_string()))?;
+        String::from_utf8(plaintext)
+            .map_err(|_| Error::Config("Decrypted secret is not valid UTF-8".to_string()))
+    }
+
+    /// Re-encrypt a value under another key; plaintext values are encrypted as is
+    pub fn reencrypt(&self, value: &str, new: &SecretsCipher) -> Result<String> {
+        if value.starts_with(ENCRYPTED_PREFIX) {
+            new.encrypt(&self.decrypt(value)?)
+        } else {
+            new.encrypt(value)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{AnchorConfig, BillingDestination, ColdTierBackend};
+    use crate::ServerConfig;
+
+    fn config_with(api_key: &str, jwt_secret: &str) -> ServerConfig {
+        let mut config: ServerConfig = serde_json::from_value(serde_json::json!({
+            "server_id": "secrets-test",
+            "neurons": [],
+        })).unwrap();
+        config.claude.api_key = Some(SecretString::new(api_key));
+        config.auth.jwt_secret = SecretString::new(jwt_secret);
+        config
+    }
+
+    /// Configs that between them set every secret field, each to a value
+    /// holding "SENTINEL"
+    fn configs_with_every_secret() -> Vec<ServerConfig> {
+        let mut config = config_with("sk-ant-SENTINEL-1", "jwt-SENTINEL-2");
+        config.receipts.signing_key = Some(SecretString::new("seed-SENTINEL-3"));
+        config.receipts.anchor = AnchorConfig::Ethereum {
+            rpc_url: "http://localhost:8545".to_string(),
+            chain_id: 1,
+            private_key: SecretString::new("eth-SENTINEL-4"),
+        };
+        config.memory.tiering.backend = ColdTierBackend::S3 {
+            endpoint: "http://localhost:9000".to_string(),
+            bucket: "cold".to_string(),
+            region: "us-east-1".to_string(),
+            prefix: String::new(),
+            access_key_id: "minio".to_string(),
+            secret_access_key: SecretString::new("s3-SENTINEL-5"),
+        };
+        config.shadow.api_key = Some(SecretString::new("hal9-SENTINEL-6"));
+
+        // Billing files go to one destination, so each kind needs a config
+        let mut webhook = config.clone();
+        config.billing_export.destination = BillingDestination::S3 {
+            endpoint: "http://localhost:9000".to_string(),
+            bucket: "billing".to_string(),
+            region: "us-east-1".to_string(),
+            prefix: String::new(),
+            access_key_id: "minio".to_string(),
+            secret_access_key: SecretString::new("s3-SENTINEL-7"),
+        };
+        webhook.billing_export.destination = BillingDestination::Webhook {
+            url: "https://billing.example/usage".to_string(),
+            secret: SecretString::new("whsec-SENTINEL-8"),
+        };
+        vec![config, webhook]
+    }
+
+    fn strings(value: &serde_json::Value, out: &mut Vec<String>) {
+        match value {
+            serde_json::Value::String(s) => out.push(s.clone()),
+            serde_json::Value::Array(items) => items.iter().for_each(|v| strings(v, out)),
+            serde_json::Value::Object(map) => map.values().for_each(|v| strings(v, out)),
+            _ => {}
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let cipher = SecretsCipher::new(&MasterKey::generate());
+        let encrypted = cipher.encrypt("sk-ant-123").unwrap();
+        assert!(encrypted.starts_with("enc:v1:"));
+        assert_ne!(cipher.encrypt("sk-ant-123").unwrap(), encrypted);
+        assert_eq!(cipher.decrypt(&encrypted).unwrap(), "sk-ant-123");
+    }
+
+    #[test]
+    fn test_wrong_key_and_corruption_fail() {
+        let cipher = SecretsCipher::new(&MasterKey::generate());
+        let other = SecretsCipher::new(&MasterKey::generate());
+        let encrypted = cipher.encrypt("sk-ant-123").unwrap();
+
+        let err = other.decrypt(&encrypted).unwrap_err().to_string();
+        assert!(err.contains("wrong master key"), "{}", err);
+
+        let mut payload = BASE64.decode(encrypted.trim_start_matches("enc:v1:")).unwrap();
+        let last = payload.len() - 1;
+        payload[last] ^= 1;
+        let tampered = format!("enc:v1:{}", BASE64.encode(payload));
+        assert!(cipher.decrypt(&tampered).unwrap_err().to_string().contains("wrong master key"));
+
+        assert!(cipher.decrypt("enc:v1:not base64!").unwrap_err().to_string().contains("Malformed"));
+        assert!(cipher.decrypt("enc:v1:AAAA").unwrap_err().to_string().contains("Malformed"));
+        assert!(cipher.decrypt(&encrypted.replacen("v1", "v9", 1)).unwrap_err().to_string().contains("version 'v9'"));
+        assert!(MasterKey::from_base64(&BASE64.encode([0u8; 16])).is_err());
+    }
+
+    #[test]
+    fn test_rotation() {
+        let old = SecretsCipher::new(&MasterKey::generate());
+        let new = SecretsCipher::new(&MasterKey::generate());
+        let encrypted = old.encrypt("jwt-secret").unwrap();
+
+        let rotated = old.reencrypt(&encrypted, &new).unwrap();
+        assert_eq!(new.decrypt(&rotated).unwrap(), "jwt-secret");
+        assert!(old.decrypt(&rotated).is_err());
+        assert_eq!(new.decrypt(&old.reencrypt("plain", &new).unwrap()).unwrap(), "plain");
+        assert!(new.reencrypt(&encrypted, &old).is_err());
+    }
+
+    #[test]
+    fn test_config_secrets_decrypted_at_load() {
+        let key = MasterKey::generate();
+        let cipher = SecretsCipher::new(&key);
+        let mut config = config_with(&cipher.encrypt("sk-ant-123").unwrap(), "plain-jwt");
+
+        config.decrypt_secrets(&StaticKeyProvider(key)).unwrap();
+        assert_eq!(config.claude.api_key.as_ref().unwrap().expose(), "sk-ant-123");
+        assert_eq!(config.auth.jwt_secret.expose(), "plain-jwt");
+
+        // Plaintext-only configs need no key
+        let mut plain = config_with("sk-plain", "jwt-plain");
+        plain.decrypt_secrets(&FileKeyProvider::new("/nonexistent/key")).unwrap();
+
+        let mut wrong = config_with(&cipher.encrypt("sk-ant-123").unwrap(), "plain-jwt");
+        let err = wrong.decrypt_secrets(&StaticKeyProvider(MasterKey::generate())).unwrap_err();
+        assert!(err.to_string().contains("claude.api_key"), "{}", err);
+    }
+
+    /// Guard for new config fields: secrets must never reach logs or
+    /// serialized output such as introspection endpoints
+    #[test]
+    fn test_no_secret_in_serialized_or_debug_config() {
+        let mut decrypted = Vec::new();
+        for mut config in configs_with_every_secret() {
+            let value = serde_json::to_value(&config).unwrap();
+            let mut found = Vec::new();
+            strings(&value, &mut found);
+            assert!(found.iter().all(|s| !s.contains("SENTINEL")), "secret serialized: {:?}", found);
+            assert!(!format!("{:?}", config).contains("SENTINEL"));
+
+            for (path, secret) in config.secrets_mut() {
+                assert!(secret.expose().contains("SENTINEL"), "{} not set by the test", path);
+                let field = path.split('.').fold(&value, |v, key| &v[key]);
+                assert_eq!(field, REDACTED, "{} not redacted", path);
+                decrypted.push(path);
+            }
+        }
+
+        assert_eq!(format!("{}", SecretString::new("jwt-secret")), REDACTED);
+        assert_eq!(format!("{:?}", MasterKey::generate()), REDACTED);
+
+        // Every listed secret field is one the config decrypts, and the
+        // other way round
+        let mut listed: Vec<String> = SECRET_FIELDS.iter().map(|path| path.join(".")).collect();
+        decrypted.sort_unstable();
+        decrypted.dedup();
+        listed.sort_unstable();
+        assert_eq!(decrypted, listed);
+    }
+}
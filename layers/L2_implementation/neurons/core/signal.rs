@@ -5,12 +5,23 @@ use uuid::Uuid;
 use chrono::{DateTime, Utc};
 use std::collections::HashMap;
 
+use crate::signal_schema::CURRENT_SCHEMA_VERSION;
+
 /// Alias for NeuronSignal for backwards compatibility
 pub type Signal = NeuronSignal;
 
 /// A signal passed between neurons
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
 pub struct NeuronSignal {
+    /// Format the signal is written in, see [`crate::signal_schema`].
+    /// Signals read from any supported version are held at the current one.
+    #[serde(
+        default = "crate::signal_schema::current",
+        deserialize_with = "crate::signal_schema::deserialize_version",
+        skip_serializing_if = "crate::signal_schema::is_unversioned"
+    )]
+    pub schema_version: u16,
     pub signal_id: Uuid,
     pub from_neuron: String,
     pub to_neuron: String,
@@ -20,14 +31,16 @@ pub struct NeuronSignal {
     pub batch_id: Uuid,
     pub timestamp: DateTime<Utc>,
     pub payload: SignalPayload,
-    /// Additional metadata for distributed routing
-    #[serde(default)]
+    /// Additional metadata for distributed routing. Keys are namespaced as
+    /// registered in [`crate::metadata_schema`]; legacy keys are renamed on read.
+    #[serde(default, deserialize_with = "crate::metadata_schema::deserialize_metadata")]
     pub metadata: HashMap<String, String>,
 }
 
 impl Default for NeuronSignal {
     fn default() -> Self {
         Self {
+            schema_version: CURRENT_SCHEMA_VERSION,
             signal_id: Uuid::new_v4(),
             from_neuron: String::new(),
             to_neuron: String::new(),
@@ -52,6 +65,7 @@ impl NeuronSignal {
         content: String,
     ) -> Self {
         Self {
+            schema_version: CURRENT_SCHEMA_VERSION,
             signal_id: Uuid::new_v4(),
             from_neuron: from.to_string(),
             to_neuron: to.to_string(),
@@ -81,6 +95,7 @@ impl NeuronSignal {
         error: Gradient,
     ) -> Self {
         Self {
+            schema_version: CURRENT_SCHEMA_VERSION,
             signal_id: Uuid::new_v4(),
             from_neuron: from.to_string(),
             to_neuron: to.to_string(),
@@ -105,6 +120,7 @@ impl NeuronSignal {
 /// Direction of signal propagation
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
 #[derive(Default)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
 pub enum PropagationType {
     /// Forward propagation (task distribution)
     #[default]
@@ -113,10 +129,20 @@ pub enum PropagationType {
     Backward,
 }
 
+impl PropagationType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PropagationType::Forward => "forward",
+            PropagationType::Backward => "backward",
+        }
+    }
+}
+
 
 /// Signal payload containing activation and optional gradient
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[derive(Default)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
 pub struct SignalPayload {
     pub activation: Activation,
     pub gradient: Option<Gradient>,
@@ -125,6 +151,7 @@ pub struct SignalPayload {
 
 /// Forward activation data
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
 pub struct Activation {
     pub content: String,
     pub strength: f32,
@@ -154,6 +181,7 @@ impl Activation {
 
 /// Backward gradient/error data
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
 pub struct Gradient {
     pub error_type: String,
     pub magnitude: f32,
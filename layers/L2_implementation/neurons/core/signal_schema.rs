@@ -0,0 +1,109 @@
+//! Versions of the serialized [`NeuronSignal`]
+//!
+//! Signals are persisted (dead letters, time travel snapshots, receipts) and
+//! sent to peers as JSON, so a server reads signals written by older
+//! releases. Every signal carries the version of the format it was written
+//! in:
+//!
+//! | version | format |
+//! |---------|--------|
+//! | 1 | the original fields, metadata under ad-hoc keys (`chain_id`, `user_id`, ...) |
+//! | 2 | metadata keys namespaced (`trace.chain_id`), see [`crate::metadata_schema`] |
+//! | 3 | `schema_version` written with every signal |
+//!
+//! Versions 1 and 2 carry no `schema_version`; such signals are read as
+//! version 1. Any version from [`MIN_SCHEMA_VERSION`] up to
+//! [`CURRENT_SCHEMA_VERSION`] is upgraded in memory as it is read: legacy
+//! metadata keys are renamed and fields added since get their serde
+//! defaults. Newer versions are refused, since their fields may mean things
+//! this release does not know. [`downgrade`] writes a signal for a reader
+//! that only knows an older version.
+//!
+//! Adding a field to [`NeuronSignal`]: give it a serde default, bump
+//! [`CURRENT_SCHEMA_VERSION`], add a row above and a step to [`downgrade`],
+//! and check a fixture of the new version in under the server's
+//! `tests/fixtures/signals/`.
+
+use std::ops::RangeInclusive;
+
+use serde::{de::Error as _, Deserialize, Deserializer};
+
+use crate::{metadata_schema, Error, NeuronSignal, Result};
+
+/// Version this release writes
+pub const CURRENT_SCHEMA_VERSION: u16 = 3;
+
+/// Oldest version this release reads
+pub const MIN_SCHEMA_VERSION: u16 = 1;
+
+/// Version of signals written without `schema_version`
+pub const UNVERSIONED: u16 = 1;
+
+/// First version that writes `schema_version`
+const VERSIONED_SINCE: u16 = 3;
+
+/// First version with namespaced metadata keys
+const NAMESPACED_METADATA_SINCE: u16 = 2;
+
+/// Versions this release reads and writes
+pub fn supported() -> RangeInclusive<u16> {
+    MIN_SCHEMA_VERSION..=CURRENT_SCHEMA_VERSION
+}
+
+/// Fail unless this release reads `version`
+pub fn check(version: u16) -> Result<()> {
+    if supported().contains(&version) {
+        Ok(())
+    } else if version > CURRENT_SCHEMA_VERSION {
+        Err(Error::Serialization(format!(
+            "Signal schema version {} is newer than this server reads (up to {})",
+            version, CURRENT_SCHEMA_VERSION
+        )))
+    } else {
+        Err(Error::Serialization(format!(
+            "Signal schema version {} is older than this server reads (from {})",
+            version, MIN_SCHEMA_VERSION
+        )))
+    }
+}
+
+/// Highest version in both `ours` and `theirs`
+pub fn negotiate(ours: RangeInclusive<u16>, theirs: RangeInclusive<u16>) -> Option<u16> {
+    let highest = (*ours.end()).min(*theirs.end());
+    (highest >= *ours.start() && highest >= *theirs.start()).then_some(highest)
+}
+
+/// `signal` as a release writing `version` would have written it. Legacy
+/// metadata keys come back below version 2; keys added since stay, and
+/// readers that do not know them ignore them.
+pub fn downgrade(signal: &NeuronSignal, version: u16) -> Result<NeuronSignal> {
+    check(version)?;
+    let mut signal = signal.clone();
+    signal.schema_version = version;
+    if version < NAMESPACED_METADATA_SINCE {
+        metadata_schema::global().read().restore_legacy(&mut signal.metadata);
+    }
+    Ok(signal)
+}
+
+/// Version a signal without `schema_version` is held at once read
+pub(crate) fn current() -> u16 {
+    CURRENT_SCHEMA_VERSION
+}
+
+/// Whether a signal held at `version` is written without `schema_version`
+pub(crate) fn is_unversioned(version: &u16) -> bool {
+    *version < VERSIONED_SINCE
+}
+
+/// Deserialize `schema_version`, refusing versions this release cannot
+/// read. The signal is upgraded as it is read, so it is held at the
+/// current version.
+pub(crate) fn deserialize_version<'de, D>(deserializer: D) -> std::result::Result<u16, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let version = u16::deserialize(deserializer)?;
+    check(version).map_err(D::Error::custom)?;
+    Ok(CURRENT_SCHEMA_VERSION)
+}
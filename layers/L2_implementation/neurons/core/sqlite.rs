@@ -0,0 +1,534 @@
+//! SQLite connection tuning shared by every SQLite-backed store
+//!
+//! Each database gets a writer pool holding a single connection and a reader
+//! pool sized for concurrent queries, both in WAL mode with a busy timeout and
+//! foreign keys enforced. Writes go through a [`WriteGate`], which serializes
+//! them and retries with jittered backoff when SQLite still reports the
+//! database busy. A read-only replica may be attached, and reads switched to
+//! it while the primary is under maintenance. After large deletions,
+//! [`SqlitePools::compact`] returns the freed pages to the filesystem.
+
+use std::future::Future;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use sqlx::sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePool, SqlitePoolOptions, SqliteSynchronous};
+use tracing::{debug, info};
+
+use crate::{Error, Result};
+
+/// Integrity check run before a database is used
+pub const QUICK_CHECK: &str = "PRAGMA quick_check";
+
+/// Statements returning the pages freed by large deletions to the
+/// filesystem, then refreshing the query planner's statistics
+pub const COMPACT: [&str; 3] = ["VACUUM", "PRAGMA wal_checkpoint(TRUNCATE)", "ANALYZE"];
+
+/// Size of the database in bytes
+pub const DATABASE_SIZE: &str = "SELECT page_count * page_size FROM pragma_page_count(), pragma_page_size()";
+
+/// Connection settings for SQLite databases
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SqliteTuning {
+    /// How long a connection waits on a lock before SQLite reports it busy
+    #[serde(default = "default_busy_timeout_ms")]
+    pub busy_timeout_ms: u64,
+
+    /// Connections in the reader pool
+    #[serde(default = "default_max_readers")]
+    pub max_readers: u32,
+
+    /// How long a query waits for a free pooled connection
+    #[serde(default = "default_acquire_timeout_ms")]
+    pub acquire_timeout_ms: u64,
+
+    /// Retries of a write that still fails busy after the busy timeout
+    #[serde(default = "default_max_busy_retries")]
+    pub max_busy_retries: u32,
+
+    /// Backoff before the first retry, doubled for each further retry
+    #[serde(default = "default_retry_base_ms")]
+    pub retry_base_ms: u64,
+}
+
+fn default_busy_timeout_ms() -> u64 {
+    5_000
+}
+
+fn default_max_readers() -> u32 {
+    std::thread::available_parallelism()
+        .map(|cores| cores.get() as u32)
+        .unwrap_or(4)
+        .clamp(2, 8)
+}
+
+fn default_acquire_timeout_ms() -> u64 {
+    30_000
+}
+
+fn default_max_busy_retries() -> u32 {
+    5
+}
+
+fn default_retry_base_ms() -> u64 {
+    20
+}
+
+impl Default for SqliteTuning {
+    fn default() -> Self {
+        Self {
+            busy_timeout_ms: default_busy_timeout_ms(),
+            max_readers: default_max_readers(),
+            acquire_timeout_ms: default_acquire_timeout_ms(),
+            max_busy_retries: default_max_busy_retries(),
+            retry_base_ms: default_retry_base_ms(),
+        }
+    }
+}
+
+impl SqliteTuning {
+    /// Connect options for `url` with WAL, the busy timeout and foreign keys applied
+    pub fn connect_options(&self, url: &str) -> Result<SqliteConnectOptions> {
+        Ok(SqliteConnectOptions::from_str(url)
+            .map_err(|e| Error::Storage(format!("Invalid SQLite URL {}: {}", url, e)))?
+            .create_if_missing(true)
+            .journal_mode(SqliteJournalMode::Wal)
+            .synchronous(SqliteSynchronous::Normal)
+            .busy_timeout(Duration::from_millis(self.busy_timeout_ms))
+            .foreign_keys(true))
+    }
+
+    /// The same settings as PRAGMA statements, for drivers without typed options
+    pub fn pragmas(&self) -> Vec<String> {
+        vec![
+            "PRAGMA journal_mode = WAL".to_string(),
+            "PRAGMA synchronous = NORMAL".to_string(),
+            format!("PRAGMA busy_timeout = {}", self.busy_timeout_ms),
+            "PRAGMA foreign_keys = ON".to_string(),
+        ]
+    }
+
+    /// Backoff before retry number `attempt`, with jitter so writers woken
+    /// together do not collide again
+    fn backoff(&self, attempt: u32) -> Duration {
+        let ceiling = self.retry_base_ms.saturating_mul(1 << attempt.saturating_sub(1).min(10)).min(1_000);
+        let jittered = rand::thread_rng().gen_range(ceiling / 2..=ceiling);
+        Duration::from_millis(jittered)
+    }
+}
+
+/// Whether an error is SQLite reporting the database busy or locked
+pub fn is_busy(error: &sqlx::Error) -> bool {
+    let sqlx::Error::Database(db_error) = error else {
+        return false;
+    };
+    // SQLITE_BUSY (5) and SQLITE_LOCKED (6), including their extended codes
+    let code = db_error.code().and_then(|code| code.parse::<i32>().ok());
+    if matches!(code.map(|code| code & 0xff), Some(5 | 6)) {
+        return true;
+    }
+    let message = db_error.message();
+    message.contains("database is locked") || message.contains("database table is locked")
+}
+
+/// Run `op`, retrying with jittered backoff while it fails busy
+pub async fn retry_busy<T, F, Fut>(tuning: &SqliteTuning, mut op: F) -> std::result::Result<T, sqlx::Error>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = std::result::Result<T, sqlx::Error>>,
+{
+    let mut attempt = 0;
+    loop {
+        match op().await {
+            Err(e) if is_busy(&e) && attempt < tuning.max_busy_retries => {
+                attempt += 1;
+                let delay = tuning.backoff(attempt);
+                debug!("SQLite busy, retrying write in {:?} (attempt {})", delay, attempt);
+                tokio::time::sleep(delay).await;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Turn the rows of [`QUICK_CHECK`] into an error naming the problems found
+pub fn check_integrity(database: &str, rows: Vec<String>) -> Result<()> {
+    if rows.iter().all(|row| row == "ok") {
+        return Ok(());
+    }
+    Err(Error::Storage(format!(
+        "SQLite database {} failed its integrity check: {}. Restore it from a backup or rebuild it with `sqlite3 <file> .recover` before starting the server",
+        database,
+        rows.join("; ")
+    )))
+}
+
+/// Serializes writes to one database and records how long they wait
+#[derive(Debug, Default)]
+pub struct WriteGate {
+    lock: tokio::sync::Mutex<()>,
+    writes: AtomicU64,
+    wait_micros: AtomicU64,
+    max_wait_micros: AtomicU64,
+    busy_retries: AtomicU64,
+}
+
+impl WriteGate {
+    /// Run a write once no other write on the database is in progress,
+    /// retrying while SQLite reports it busy
+    pub async fn run<T, F, Fut>(&self, tuning: &SqliteTuning, mut op: F) -> std::result::Result<T, sqlx::Error>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = std::result::Result<T, sqlx::Error>>,
+    {
+        let queued = Instant::now();
+        let _guard = self.lock.lock().await;
+        let waited = queued.elapsed().as_micros() as u64;
+        self.writes.fetch_add(1, Ordering::Relaxed);
+        self.wait_micros.fetch_add(waited, Ordering::Relaxed);
+        self.max_wait_micros.fetch_max(waited, Ordering::Relaxed);
+
+        let mut attempts = 0;
+        let result = retry_busy(tuning, || {
+            attempts += 1;
+            op()
+        })
+        .await;
+        self.busy_retries.fetch_add(attempts - 1, Ordering::Relaxed);
+        result
+    }
+
+    /// Fill in the write counters of `stats`
+    pub fn fill(&self, stats: &mut PoolStats) {
+        let writes = self.writes.load(Ordering::Relaxed);
+        stats.writes = writes;
+        stats.write_wait_ms_avg = if writes == 0 {
+            0.0
+        } else {
+            self.wait_micros.load(Ordering::Relaxed) as f64 / writes as f64 / 1000.0
+        };
+        stats.write_wait_ms_max = self.max_wait_micros.load(Ordering::Relaxed) as f64 / 1000.0;
+        stats.busy_retries = self.busy_retries.load(Ordering::Relaxed);
+    }
+}
+
+/// Connection usage and write contention of a database
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PoolStats {
+    pub reader_in_use: u32,
+    pub reader_idle: u32,
+    pub writer_in_use: u32,
+    pub writer_idle: u32,
+    pub writes: u64,
+    /// Time writes spent queued behind other writes
+    pub write_wait_ms_avg: f64,
+    pub write_wait_ms_max: f64,
+    pub busy_retries: u64,
+}
+
+/// Size of a database before and after [`COMPACT`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CompactReport {
+    pub database: String,
+    pub bytes_before: i64,
+    pub bytes_after: i64,
+    pub duration_ms: u64,
+}
+
+/// Reader and writer pools for one SQLite database
+#[derive(Debug, Clone)]
+pub struct SqlitePools {
+    reader: SqlitePool,
+    writer: SqlitePool,
+    gate: Arc<WriteGate>,
+    tuning: SqliteTuning,
+    /// Read-only copy of the database, shared by every clone
+    replica: Arc<OnceLock<SqlitePool>>,
+    /// Whether reads go to the replica
+    replica_reads: Arc<AtomicBool>,
+}
+
+impl SqlitePools {
+    /// Open `url` with separate reader and writer pools. In-memory databases
+    /// exist per connection, so they get one connection shared by both.
+    pub async fn connect(url: &str, tuning: SqliteTuning) -> Result<Self> {
+        let options = tuning.connect_options(url)?;
+        let acquire_timeout = Duration::from_millis(tuning.acquire_timeout_ms);
+        let connect_error = |e: sqlx::Error| Error::Storage(format!("Failed to connect to SQLite {}: {}", url, e));
+
+        let writer = SqlitePoolOptions::new()
+            .max_connections(1)
+            .acquire_timeout(acquire_timeout)
+            .connect_with(options.clone())
+            .await
+            .map_err(connect_error)?;
+
+        let reader = if is_in_memory(url) {
+            writer.clone()
+        } else {
+            SqlitePoolOptions::new()
+                .max_connections(tuning.max_readers)
+                .acquire_timeout(acquire_timeout)
+                .connect_with(options)
+                .await
+                .map_err(connect_error)?
+        };
+
+        info!("Opened SQLite {} with {} reader connections", url, reader.options().get_max_connections());
+        Ok(Self {
+            reader,
+            writer,
+            gate: Arc::new(WriteGate::default()),
+            tuning,
+            replica: Arc::default(),
+            replica_reads: Arc::default(),
+        })
+    }
+
+    /// Open `url` read-only as the replica reads can be switched to with
+    /// [`set_replica_reads`](Self::set_replica_reads). Every clone of these
+    /// pools shares the replica.
+    pub async fn attach_replica(&self, url: &str) -> Result<()> {
+        let options = SqliteConnectOptions::from_str(url)
+            .map_err(|e| Error::Storage(format!("Invalid SQLite URL {}: {}", url, e)))?
+            .read_only(true)
+            .busy_timeout(Duration::from_millis(self.tuning.busy_timeout_ms));
+        let replica = SqlitePoolOptions::new()
+            .max_connections(self.tuning.max_readers)
+            .acquire_timeout(Duration::from_millis(self.tuning.acquire_timeout_ms))
+            .connect_with(options)
+            .await
+            .map_err(|e| Error::Storage(format!("Failed to connect to SQLite replica {}: {}", url, e)))?;
+        self.replica
+            .set(replica)
+            .map_err(|_| Error::Storage("A replica is already attached".to_string()))?;
+        info!("Attached SQLite replica {}", url);
+        Ok(())
+    }
+
+    /// Send reads to the replica, or back to the primary. Returns whether
+    /// reads now go to the replica, which is never the case without one.
+    pub fn set_replica_reads(&self, enabled: bool) -> bool {
+        let enabled = enabled && self.replica.get().is_some();
+        self.replica_reads.store(enabled, Ordering::Relaxed);
+        enabled
+    }
+
+    /// Whether reads go to the replica
+    pub fn replica_reads(&self) -> bool {
+        self.replica_reads.load(Ordering::Relaxed)
+    }
+
+    /// Pool for queries that only read: the replica while reads are
+    /// switched to it, otherwise the primary's reader pool
+    pub fn reader(&self) -> &SqlitePool {
+        match self.replica.get() {
+            Some(replica) if self.replica_reads() => replica,
+            _ => &self.reader,
+        }
+    }
+
+    /// Pool holding the single writer connection
+    pub fn writer(&self) -> &SqlitePool {
+        &self.writer
+    }
+
+    /// Run a write or write transaction on the writer pool, retrying with
+    /// jittered backoff while the database is busy
+    pub async fn write<T, F, Fut>(&self, mut op: F) -> std::result::Result<T, sqlx::Error>
+    where
+        F: FnMut(SqlitePool) -> Fut,
+        Fut: Future<Output = std::result::Result<T, sqlx::Error>>,
+    {
+        self.gate.run(&self.tuning, || op(self.writer.clone())).await
+    }
+
+    /// Run `PRAGMA quick_check`, failing with the problems found
+    pub async fn quick_check(&self, database: &str) -> Result<()> {
+        let rows: Vec<String> = sqlx::query_scalar(QUICK_CHECK)
+            .fetch_all(&self.reader)
+            .await
+            .map_err(|e| Error::Storage(format!("Failed to check integrity of SQLite {}: {}", database, e)))?;
+        check_integrity(database, rows)
+    }
+
+    /// Run [`COMPACT`] on the writer. Other writes wait until it is done.
+    pub async fn compact(&self, database: &str) -> Result<CompactReport> {
+        let started = Instant::now();
+        let failed = |e: sqlx::Error| Error::Storage(format!("Failed to compact SQLite {}: {}", database, e));
+        let bytes_before: i64 = sqlx::query_scalar(DATABASE_SIZE).fetch_one(&self.writer).await.map_err(failed)?;
+        for statement in COMPACT {
+            self.write(|pool| async move { sqlx::query(statement).execute(&pool).await })
+                .await
+                .map_err(failed)?;
+        }
+        let bytes_after: i64 = sqlx::query_scalar(DATABASE_SIZE).fetch_one(&self.writer).await.map_err(failed)?;
+        info!("Compacted SQLite {} from {} to {} bytes", database, bytes_before, bytes_after);
+        Ok(CompactReport {
+            database: database.to_string(),
+            bytes_before,
+            bytes_after,
+            duration_ms: started.elapsed().as_millis() as u64,
+        })
+    }
+
+    /// Current connection usage and write contention
+    pub fn stats(&self) -> PoolStats {
+        let mut stats = PoolStats {
+            reader_in_use: self.reader.size() - self.reader.num_idle() as u32,
+            reader_idle: self.reader.num_idle() as u32,
+            writer_in_use: self.writer.size() - self.writer.num_idle() as u32,
+            writer_idle: self.writer.num_idle() as u32,
+            ..Default::default()
+        };
+        self.gate.fill(&mut stats);
+        stats
+    }
+
+    /// Close both pools
+    pub async fn close(&self) {
+        self.writer.close().await;
+        self.reader.close().await;
+        if let Some(replica) = self.replica.get() {
+            replica.close().await;
+        }
+    }
+}
+
+/// A single existing pool serves both reads and writes. Writes are still
+/// serialized and retried, but the pool keeps its own connect options.
+impl From<SqlitePool> for SqlitePools {
+    fn from(pool: SqlitePool) -> Self {
+        Self {
+            reader: pool.clone(),
+            writer: pool,
+            gate: Arc::new(WriteGate::default()),
+            tuning: SqliteTuning::default(),
+            replica: Arc::default(),
+            replica_reads: Arc::default(),
+        }
+    }
+}
+
+fn is_in_memory(url: &str) -> bool {
+    url.contains(":memory:") || url.contains("mode=memory")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_stays_within_jitter_bounds() {
+        let tuning = SqliteTuning::default();
+        for attempt in 1..=8 {
+            let ceiling = (20u64 << (attempt - 1)).min(1_000);
+            let delay = tuning.backoff(attempt).as_millis() as u64;
+            assert!(delay >= ceiling / 2 && delay <= ceiling, "attempt {} waited {}ms", attempt, delay);
+        }
+    }
+
+    #[test]
+    fn test_integrity_check_names_problems() {
+        assert!(check_integrity("hal9.db", vec!["ok".to_string()]).is_ok());
+        let error = check_integrity("hal9.db", vec!["row 3 missing from index idx_signals".to_string()])
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("hal9.db"));
+        assert!(error.contains("idx_signals"));
+    }
+
+    #[tokio::test]
+    async fn test_file_database_uses_wal_and_separate_pools() {
+        let path = std::env::temp_dir().join(format!("hal9-tuned-{}.db", uuid::Uuid::new_v4()));
+        let url = format!("sqlite:{}", path.display());
+        let pools = SqlitePools::connect(&url, SqliteTuning::default()).await.unwrap();
+
+        let mode: String = sqlx::query_scalar("PRAGMA journal_mode").fetch_one(pools.reader()).await.unwrap();
+        assert_eq!(mode, "wal");
+        let foreign_keys: i64 = sqlx::query_scalar("PRAGMA foreign_keys").fetch_one(pools.writer()).await.unwrap();
+        assert_eq!(foreign_keys, 1);
+        assert_eq!(pools.writer().options().get_max_connections(), 1);
+        pools.quick_check("tuned.db").await.unwrap();
+
+        pools
+            .write(|pool| async move { sqlx::query("CREATE TABLE t (id INTEGER)").execute(&pool).await })
+            .await
+            .unwrap();
+        assert_eq!(pools.stats().writes, 1);
+        pools.close().await;
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn test_compact_returns_freed_pages() {
+        let path = std::env::temp_dir().join(format!("hal9-compact-{}.db", uuid::Uuid::new_v4()));
+        let pools = SqlitePools::connect(&format!("sqlite:{}", path.display()), SqliteTuning::default()).await.unwrap();
+        pools
+            .write(|pool| async move {
+                sqlx::query("CREATE TABLE t (payload TEXT)").execute(&pool).await?;
+                sqlx::query(
+                    "WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < 2000) \
+                     INSERT INTO t SELECT hex(randomblob(512)) FROM n",
+                )
+                .execute(&pool)
+                .await?;
+                sqlx::query("DELETE FROM t").execute(&pool).await
+            })
+            .await
+            .unwrap();
+
+        let report = pools.compact("compact.db").await.unwrap();
+        assert_eq!(report.database, "compact.db");
+        assert!(report.bytes_after < report.bytes_before / 10, "{:?}", report);
+        pools.close().await;
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn test_reads_switch_to_attached_replica() {
+        let dir = std::env::temp_dir();
+        let primary_path = dir.join(format!("hal9-primary-{}.db", uuid::Uuid::new_v4()));
+        let replica_path = dir.join(format!("hal9-replica-{}.db", uuid::Uuid::new_v4()));
+        let mut databases = Vec::new();
+        for (path, name) in [(&primary_path, "primary"), (&replica_path, "replica")] {
+            let pools = SqlitePools::connect(&format!("sqlite:{}", path.display()), SqliteTuning::default()).await.unwrap();
+            pools
+                .write(|pool| async move {
+                    sqlx::query("CREATE TABLE t (name TEXT)").execute(&pool).await?;
+                    sqlx::query("INSERT INTO t VALUES (?)").bind(name).execute(&pool).await
+                })
+                .await
+                .unwrap();
+            databases.push(pools);
+        }
+        let pools = databases.remove(0);
+        let read = |pools: SqlitePools| async move {
+            sqlx::query_scalar::<_, String>("SELECT name FROM t").fetch_one(pools.reader()).await.unwrap()
+        };
+
+        // Without a replica reads stay on the primary
+        assert!(!pools.set_replica_reads(true));
+        assert_eq!(read(pools.clone()).await, "primary");
+
+        let clone = pools.clone();
+        pools.attach_replica(&format!("sqlite:{}", replica_path.display())).await.unwrap();
+        assert!(pools.set_replica_reads(true));
+        assert!(clone.replica_reads());
+        assert_eq!(read(clone.clone()).await, "replica");
+        // The replica is opened read-only
+        assert!(sqlx::query("INSERT INTO t VALUES ('x')").execute(pools.reader()).await.is_err());
+
+        assert!(!pools.set_replica_reads(false));
+        assert_eq!(read(clone).await, "primary");
+
+        pools.close().await;
+        databases[0].close().await;
+        let _ = std::fs::remove_file(&primary_path);
+        let _ = std::fs::remove_file(&replica_path);
+    }
+}
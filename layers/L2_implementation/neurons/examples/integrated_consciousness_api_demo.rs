@@ -33,6 +33,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         update_interval_ms: 100,
         enable_claude: true,
         enable_streaming: true,
+        compression: Default::default(),
     };
     
     // Build integrated system with neurons
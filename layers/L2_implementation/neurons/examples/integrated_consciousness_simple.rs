@@ -8,7 +8,7 @@
 use std::sync::Arc;
 use std::collections::HashMap;
 use hal9_core::{
-    consciousness::{ConsciousnessMonitor, BoundaryNetwork, ConsciousnessPhase},
+    consciousness::{ConsciousnessMonitor, BoundaryNetwork, ConsciousnessPhase, TrafficSnapshot},
     Layer, Neuron, NeuronId,
 };
 
@@ -157,7 +157,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Phase 4: Compression boundaries
     println!("\n📍 Phase 4: Analyzing compression boundaries...");
     let mut boundary_network = BoundaryNetwork::new();
-    boundary_network.update(&neurons).await;
+    boundary_network.update(&TrafficSnapshot::default());
     
     println!("\n{}", boundary_network.full_report());
     
@@ -209,7 +209,7 @@ mod tests {
         
         // Test boundary network
         let mut boundaries = BoundaryNetwork::new();
-        boundaries.update(&neurons).await;
+        boundaries.update(&TrafficSnapshot::default());
         
         // Should have boundaries
         assert!(boundaries.hottest_boundary().is_some());
@@ -7,7 +7,7 @@ use std::sync::Arc;
 use std::collections::HashMap;
 use tokio::sync::RwLock;
 use hal9_core::{
-    consciousness::{ConsciousnessMonitor, BoundaryNetwork, GOLDEN_RATIO},
+    consciousness::{ConsciousnessMonitor, BoundaryNetwork, TrafficSnapshot, GOLDEN_RATIO},
     Layer, Neuron, NeuronId, Signal,
 };
 
@@ -158,7 +158,7 @@ impl ConsciousnessExperiment {
         
         for cycle in 0..20 {
             // Update boundaries
-            boundaries.update(&neurons).await;
+            boundaries.update(&TrafficSnapshot::default());
             
             // Measure consciousness
             let metrics = monitor.measure(&neurons).await;
@@ -1,6 +1,7 @@
 // Desktop version of Ultima Offline PAL Edition
 // Run with: cargo run --bin ultima-pal-desktop --features desktop
 
+use ultima_offline_pal::bridge::{BridgeConfig, EventBridge, HttpSink};
 use ultima_offline_pal::game::PAL9Neuron;
 
 use crossterm::{
@@ -12,7 +13,29 @@ use crossterm::{
 };
 
 use std::io::{self, Write};
-use std::time::Duration;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// Set HAL9_SERVER_URL to stream game events to a running HAL9 server
+fn event_bridge() -> EventBridge {
+    match std::env::var("HAL9_SERVER_URL") {
+        Ok(url) => {
+            let mut bridge = EventBridge::new(BridgeConfig {
+                enabled: true,
+                ..Default::default()
+            });
+            bridge.set_sink(Box::new(HttpSink::new(&url, std::env::var("HAL9_API_KEY").ok())));
+            bridge
+        }
+        Err(_) => EventBridge::disabled(),
+    }
+}
+
+fn now_ms() -> f64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as f64)
+        .unwrap_or(0.0)
+}
 
 fn main() -> io::Result<()> {
     println!("Ultima Offline PAL Edition v0.001");
@@ -30,6 +53,7 @@ fn main() -> io::Result<()> {
     execute!(stdout, terminal::Clear(ClearType::All))?;
     
     let mut neuron = PAL9Neuron::new();
+    let mut bridge = event_bridge();
     
     loop {
         // Clear and render
@@ -98,6 +122,7 @@ fn main() -> io::Result<()> {
         
         // Think
         neuron.think();
+        bridge.publish(neuron.drain_events(), now_ms());
     }
     
     // Cleanup
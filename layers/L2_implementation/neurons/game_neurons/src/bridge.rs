@@ -0,0 +1,436 @@
+// Event bridge between the game and a running HAL9 network
+// Outbound: significant game events become NeuronSignals handed to a sink
+// Inbound: signals from HAL9 become actions the game applies on think()
+
+use hal9_core::{metadata_schema::keys, NeuronSignal};
+use serde::{Serialize, Deserialize};
+
+/// Awareness levels that emit an event the first time they are crossed
+pub const AWARENESS_THRESHOLDS: [f64; 5] = [0.1, 0.3, 0.5, 0.7, 0.9];
+
+/// A significant moment in the game worth telling HAL9 about
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum GameEvent {
+    AwarenessThreshold {
+        threshold: f64,
+        awareness: f64,
+        turn: u64,
+    },
+    RealityGlitch {
+        kind: String,
+        reality_integrity: f64,
+        turn: u64,
+    },
+    PlayerDeath {
+        killed_by: String,
+        turn: u64,
+    },
+    NpcConversation {
+        npc: String,
+        line: String,
+        dialogue_state: usize,
+        turn: u64,
+    },
+}
+
+impl GameEvent {
+    /// Short name used in signal metadata
+    pub fn kind(&self) -> &'static str {
+        match self {
+            GameEvent::AwarenessThreshold { .. } => "awareness_threshold",
+            GameEvent::RealityGlitch { .. } => "reality_glitch",
+            GameEvent::PlayerDeath { .. } => "player_death",
+            GameEvent::NpcConversation { .. } => "npc_conversation",
+        }
+    }
+
+    /// HAL9 layer the event is addressed to. Deaths are reflexive, glitches
+    /// are operational, conversations are tactical and awareness belongs to
+    /// the top of the hierarchy.
+    pub fn target_layer(&self) -> &'static str {
+        match self {
+            GameEvent::PlayerDeath { .. } => "L1",
+            GameEvent::RealityGlitch { .. } => "L3",
+            GameEvent::NpcConversation { .. } => "L4",
+            GameEvent::AwarenessThreshold { .. } => "L9",
+        }
+    }
+
+    /// Wrap the event in a forward signal from the game neuron
+    pub fn to_signal(&self, config: &BridgeConfig) -> NeuronSignal {
+        let content = serde_json::to_string(self).unwrap_or_default();
+        let mut signal = NeuronSignal::forward(
+            &config.source_neuron,
+            &config.target_neuron,
+            &config.source_layer,
+            self.target_layer(),
+            content,
+        );
+        signal.metadata.insert(keys::game::SOURCE.to_string(), "ultima-offline-pal".to_string());
+        signal.metadata.insert(keys::game::EVENT.to_string(), self.kind().to_string());
+        signal
+    }
+}
+
+/// Something HAL9 asked the game to do
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum InboundAction {
+    SpawnEntity {
+        glyph: char,
+        name: String,
+        from_universe: String,
+        #[serde(default = "default_spawn_hp")]
+        hp: i32,
+    },
+    Message {
+        text: String,
+    },
+    AdjustReality {
+        delta: f64,
+    },
+    AdjustAwareness {
+        delta: f64,
+    },
+}
+
+fn default_spawn_hp() -> i32 {
+    10
+}
+
+impl InboundAction {
+    /// Parse a serialized NeuronSignal whose activation content is an action
+    pub fn from_signal_json(json: &str) -> Result<Self, String> {
+        let signal: NeuronSignal = serde_json::from_str(json)
+            .map_err(|e| format!("Invalid signal: {}", e))?;
+        serde_json::from_str(&signal.payload.activation.content)
+            .map_err(|e| format!("Invalid action in signal content: {}", e))
+    }
+}
+
+/// Where outbound signals go: a JS callback, an HTTP endpoint, a test buffer
+pub trait EventSink {
+    fn send(&mut self, signal: NeuronSignal) -> Result<(), String>;
+}
+
+#[derive(Clone, Debug)]
+pub struct BridgeConfig {
+    /// Off by default so the game plays offline exactly as before
+    pub enabled: bool,
+    pub source_neuron: String,
+    pub source_layer: String,
+    pub target_neuron: String,
+    /// Token bucket size
+    pub burst: u32,
+    /// Tokens restored per second
+    pub events_per_second: f64,
+}
+
+impl Default for BridgeConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            source_neuron: "pal9".to_string(),
+            source_layer: "L2".to_string(),
+            target_neuron: "hal9".to_string(),
+            burst: 5,
+            events_per_second: 1.0,
+        }
+    }
+}
+
+/// Token bucket driven by caller-supplied milliseconds, since wasm32 has
+/// no monotonic clock in std
+struct RateLimiter {
+    tokens: f64,
+    last_ms: Option<f64>,
+}
+
+impl RateLimiter {
+    fn new(burst: u32) -> Self {
+        Self {
+            tokens: burst as f64,
+            last_ms: None,
+        }
+    }
+
+    fn try_acquire(&mut self, now_ms: f64, config: &BridgeConfig) -> bool {
+        if let Some(last) = self.last_ms {
+            let elapsed = (now_ms - last).max(0.0) / 1000.0;
+            self.tokens = (self.tokens + elapsed * config.events_per_second).min(config.burst as f64);
+        }
+        self.last_ms = Some(now_ms);
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+pub struct EventBridge {
+    config: BridgeConfig,
+    sink: Option<Box<dyn EventSink>>,
+    limiter: RateLimiter,
+    sent: u64,
+    dropped: u64,
+}
+
+impl EventBridge {
+    pub fn new(config: BridgeConfig) -> Self {
+        let limiter = RateLimiter::new(config.burst);
+        Self {
+            config,
+            sink: None,
+            limiter,
+            sent: 0,
+            dropped: 0,
+        }
+    }
+
+    /// A bridge that never sends anything
+    pub fn disabled() -> Self {
+        Self::new(BridgeConfig::default())
+    }
+
+    pub fn set_sink(&mut self, sink: Box<dyn EventSink>) {
+        self.sink = Some(sink);
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.config.enabled = enabled;
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.config.enabled && self.sink.is_some()
+    }
+
+    pub fn config(&self) -> &BridgeConfig {
+        &self.config
+    }
+
+    /// Forward events to the sink, dropping whatever exceeds the rate limit
+    pub fn publish(&mut self, events: Vec<GameEvent>, now_ms: f64) {
+        if !self.is_enabled() {
+            return;
+        }
+
+        for event in events {
+            if !self.limiter.try_acquire(now_ms, &self.config) {
+                self.dropped += 1;
+                continue;
+            }
+
+            let signal = event.to_signal(&self.config);
+            let sent = match self.sink.as_mut() {
+                Some(sink) => sink.send(signal).is_ok(),
+                None => false,
+            };
+            if sent {
+                self.sent += 1;
+            } else {
+                self.dropped += 1;
+            }
+        }
+    }
+
+    pub fn sent(&self) -> u64 {
+        self.sent
+    }
+
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+}
+
+/// Posts events to the HAL9 signal API from a background thread so the
+/// render loop never waits on the network
+#[cfg(feature = "desktop")]
+pub struct HttpSink {
+    tx: std::sync::mpsc::Sender<NeuronSignal>,
+}
+
+#[cfg(feature = "desktop")]
+impl HttpSink {
+    /// `server_url` is the HAL9 server root, e.g. http://localhost:8080
+    pub fn new(server_url: &str, api_key: Option<String>) -> Self {
+        let (tx, rx) = std::sync::mpsc::channel::<NeuronSignal>();
+        let endpoint = format!("{}/api/v1/signal", server_url.trim_end_matches('/'));
+
+        std::thread::spawn(move || {
+            let client = reqwest::blocking::Client::new();
+            for signal in rx {
+                let body = serde_json::json!({
+                    "content": signal.payload.activation.content,
+                    "layer": signal.layer_to,
+                    "neuron_id": signal.to_neuron,
+                });
+                let mut request = client.post(&endpoint).json(&body);
+                if let Some(key) = &api_key {
+                    request = request.header("X-API-Key", key);
+                }
+                // Losing an event is fine; the game must keep running
+                let _ = request.send();
+            }
+        });
+
+        Self { tx }
+    }
+}
+
+#[cfg(feature = "desktop")]
+impl EventSink for HttpSink {
+    fn send(&mut self, signal: NeuronSignal) -> Result<(), String> {
+        self.tx.send(signal).map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::PAL9Neuron;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Clone, Default)]
+    struct BufferSink(Arc<Mutex<Vec<NeuronSignal>>>);
+
+    impl EventSink for BufferSink {
+        fn send(&mut self, signal: NeuronSignal) -> Result<(), String> {
+            self.0.lock().unwrap().push(signal);
+            Ok(())
+        }
+    }
+
+    fn round_trip(event: GameEvent, kind: &str, layer: &str) {
+        let config = BridgeConfig::default();
+        let signal = event.to_signal(&config);
+
+        assert_eq!(signal.from_neuron, "pal9");
+        assert_eq!(signal.layer_from, "L2");
+        assert_eq!(signal.layer_to, layer);
+        assert_eq!(signal.metadata.get(keys::game::EVENT).map(String::as_str), Some(kind));
+
+        // The signal itself must survive the wire
+        let wire = serde_json::to_string(&signal).unwrap();
+        let decoded: NeuronSignal = serde_json::from_str(&wire).unwrap();
+        let content: serde_json::Value = serde_json::from_str(&decoded.payload.activation.content).unwrap();
+        assert_eq!(content["event"], kind);
+
+        let parsed: GameEvent = serde_json::from_str(&decoded.payload.activation.content).unwrap();
+        assert_eq!(parsed, event);
+    }
+
+    #[test]
+    fn test_awareness_threshold_serialization() {
+        round_trip(
+            GameEvent::AwarenessThreshold { threshold: 0.5, awareness: 0.51, turn: 42 },
+            "awareness_threshold",
+            "L9",
+        );
+    }
+
+    #[test]
+    fn test_reality_glitch_serialization() {
+        round_trip(
+            GameEvent::RealityGlitch { kind: "time_reversal".to_string(), reality_integrity: 0.4, turn: 7 },
+            "reality_glitch",
+            "L3",
+        );
+    }
+
+    #[test]
+    fn test_player_death_serialization() {
+        round_trip(
+            GameEvent::PlayerDeath { killed_by: "Zergling".to_string(), turn: 99 },
+            "player_death",
+            "L1",
+        );
+    }
+
+    #[test]
+    fn test_npc_conversation_serialization() {
+        round_trip(
+            GameEvent::NpcConversation {
+                npc: "Professor Kim".to_string(),
+                line: "Kim: 'Wait... have we met before?'".to_string(),
+                dialogue_state: 2,
+                turn: 3,
+            },
+            "npc_conversation",
+            "L4",
+        );
+    }
+
+    #[test]
+    fn test_disabled_bridge_sends_nothing() {
+        let sink = BufferSink::default();
+        let mut bridge = EventBridge::disabled();
+        bridge.set_sink(Box::new(sink.clone()));
+
+        bridge.publish(vec![GameEvent::PlayerDeath { killed_by: "grue".to_string(), turn: 1 }], 0.0);
+
+        assert!(sink.0.lock().unwrap().is_empty());
+        assert_eq!(bridge.sent(), 0);
+    }
+
+    #[test]
+    fn test_rate_limit_drops_excess_events() {
+        let sink = BufferSink::default();
+        let mut bridge = EventBridge::new(BridgeConfig {
+            enabled: true,
+            burst: 2,
+            events_per_second: 1.0,
+            ..Default::default()
+        });
+        bridge.set_sink(Box::new(sink.clone()));
+
+        let burst: Vec<_> = (0..5)
+            .map(|turn| GameEvent::PlayerDeath { killed_by: "grue".to_string(), turn })
+            .collect();
+        bridge.publish(burst, 0.0);
+        assert_eq!(bridge.sent(), 2);
+        assert_eq!(bridge.dropped(), 3);
+
+        // One second later one more token is available
+        bridge.publish(vec![GameEvent::PlayerDeath { killed_by: "grue".to_string(), turn: 5 }], 1000.0);
+        assert_eq!(sink.0.lock().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_inbound_signal_spawns_entity() {
+        let mut neuron = PAL9Neuron::new();
+        let before = neuron.monster_count();
+
+        let action = r#"{"action":"spawn_entity","glyph":"P","name":"Protoss Zealot","from_universe":"StarCraft"}"#;
+        let signal = NeuronSignal::forward("hal9", "pal9", "L4", "L2", action.to_string());
+        neuron.queue_signal(&serde_json::to_string(&signal).unwrap()).unwrap();
+
+        // Nothing changes until the neuron thinks
+        assert_eq!(neuron.monster_count(), before);
+        neuron.think();
+        assert_eq!(neuron.monster_count(), before + 1);
+    }
+
+    #[test]
+    fn test_inbound_signal_adjusts_reality() {
+        let mut neuron = PAL9Neuron::new();
+        let action = r#"{"action":"adjust_reality","delta":-0.3}"#;
+        let signal = NeuronSignal::forward("hal9", "pal9", "L4", "L2", action.to_string());
+        neuron.queue_signal(&serde_json::to_string(&signal).unwrap()).unwrap();
+        neuron.think();
+
+        assert!((neuron.get_display().reality_integrity - 0.43).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_malformed_inbound_signal_is_rejected() {
+        let mut neuron = PAL9Neuron::new();
+        assert!(neuron.queue_signal("not json").is_err());
+
+        let signal = NeuronSignal::forward("hal9", "pal9", "L4", "L2", "hello".to_string());
+        assert!(neuron.queue_signal(&serde_json::to_string(&signal).unwrap()).is_err());
+    }
+}
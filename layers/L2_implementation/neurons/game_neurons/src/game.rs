@@ -4,10 +4,16 @@
 use serde::{Serialize, Deserialize};
 use rand::{Rng, SeedableRng};
 use rand::rngs::StdRng;
+use std::collections::VecDeque;
+
+use crate::bridge::{GameEvent, InboundAction, AWARENESS_THRESHOLDS};
 
 pub const GRID_WIDTH: usize = 80;
 pub const GRID_HEIGHT: usize = 24;
 
+// Events nobody drains are dropped oldest-first past this point
+const MAX_PENDING_EVENTS: usize = 64;
+
 #[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
 pub enum Tile {
     Floor,
@@ -107,6 +113,10 @@ pub struct PAL9Neuron {
     state: GameState,
     rng: StdRng,
     glitch_accumulator: f64,
+    // Awareness at the last threshold check
+    last_awareness: f64,
+    events: Vec<GameEvent>,
+    inbound: VecDeque<InboundAction>,
 }
 
 impl Default for PAL9Neuron {
@@ -138,6 +148,9 @@ impl PAL9Neuron {
             },
             rng: StdRng::from_entropy(),
             glitch_accumulator: 0.0,
+            last_awareness: 0.001,
+            events: Vec::new(),
+            inbound: VecDeque::new(),
         };
         
         neuron.generate_dungeon();
@@ -159,6 +172,7 @@ impl PAL9Neuron {
         
         self.update_monsters();
         self.check_glitches();
+        self.check_awareness_thresholds();
         self.state.turn_count += 1;
     }
     
@@ -206,8 +220,29 @@ impl PAL9Neuron {
     }
     
     pub fn think(&mut self) {
+        while let Some(action) = self.inbound.pop_front() {
+            self.apply_inbound(action);
+        }
+        
         self.state.awareness += 0.0001;
         self.glitch_accumulator += self.state.awareness * 0.01;
+        self.check_awareness_thresholds();
+    }
+    
+    /// Queue a serialized NeuronSignal from HAL9; it takes effect on the next think()
+    pub fn queue_signal(&mut self, json: &str) -> Result<(), String> {
+        let action = InboundAction::from_signal_json(json)?;
+        self.inbound.push_back(action);
+        Ok(())
+    }
+    
+    /// Take every event emitted since the last call
+    pub fn drain_events(&mut self) -> Vec<GameEvent> {
+        std::mem::take(&mut self.events)
+    }
+    
+    pub fn monster_count(&self) -> usize {
+        self.state.monsters.len()
     }
     
     pub fn should_glitch(&self) -> bool {
@@ -226,6 +261,7 @@ impl PAL9Neuron {
         match serde_json::from_str(data) {
             Ok(state) => {
                 self.state = state;
+                self.last_awareness = self.state.awareness;
                 self.add_message("Save loaded. But do you remember making it?".to_string());
                 Ok(())
             }
@@ -383,6 +419,11 @@ impl PAL9Neuron {
                 let new_x = (monster_x as i32 + dx) as usize;
                 let new_y = (monster_y as i32 + dy) as usize;
                 
+                if new_x == self.state.player_x && new_y == self.state.player_y {
+                    self.monster_attacks(idx);
+                    continue;
+                }
+                
                 if new_x < GRID_WIDTH && new_y < GRID_HEIGHT 
                     && self.state.grid.get(new_y, new_x) != Tile::Wall {
                     self.state.monsters[idx].x = new_x;
@@ -392,6 +433,32 @@ impl PAL9Neuron {
         }
     }
     
+    fn monster_attacks(&mut self, idx: usize) {
+        let damage = self.rng.gen_range(1..4);
+        let name = self.state.monsters[idx].name.clone();
+        self.state.player_hp -= damage;
+        
+        if self.state.player_hp <= 0 {
+            self.player_dies(name);
+        } else {
+            self.add_message(format!("The {} hits you.", name));
+        }
+    }
+    
+    fn player_dies(&mut self, killed_by: String) {
+        self.add_message(format!("You were killed by the {}. You wake up. Again.", killed_by));
+        self.emit(GameEvent::PlayerDeath {
+            killed_by,
+            turn: self.state.turn_count,
+        });
+        
+        self.state.player_hp = self.state.player_max_hp;
+        self.state.player_x = 40;
+        self.state.player_y = 12;
+        self.state.awareness += 0.05;
+        self.update_visibility();
+    }
+    
     fn update_visibility(&mut self) {
         for y in 0..GRID_HEIGHT {
             for x in 0..GRID_WIDTH {
@@ -448,7 +515,7 @@ impl PAL9Neuron {
             }
         };
         
-        self.add_message(message);
+        self.add_message(message.clone());
         self.state.awareness += awareness_delta;
         
         let npc = &mut self.state.npcs[npc_index];
@@ -456,6 +523,14 @@ impl PAL9Neuron {
             npc.dialogue_state = new_state;
         }
         npc.awareness += npc_awareness_delta;
+        
+        let event = GameEvent::NpcConversation {
+            npc: npc.name.clone(),
+            line: message,
+            dialogue_state: npc.dialogue_state,
+            turn: self.state.turn_count,
+        };
+        self.emit(event);
     }
     
     fn check_glitches(&mut self) {
@@ -465,12 +540,13 @@ impl PAL9Neuron {
     }
     
     fn trigger_random_glitch(&mut self) {
-        match self.rng.gen_range(0..4) {
-            0 => self.trigger_time_reversal(),
-            1 => self.trigger_memory_corruption(),
-            2 => self.spawn_wrong_universe_entity(),
-            _ => self.trigger_cmos_failure(),
-        }
+        let kind = match self.rng.gen_range(0..4) {
+            0 => { self.trigger_time_reversal(); "time_reversal" }
+            1 => { self.trigger_memory_corruption(); "memory_corruption" }
+            2 => { self.spawn_wrong_universe_entity(); "wrong_universe_entity" }
+            _ => { self.trigger_cmos_failure(); "cmos_failure" }
+        };
+        self.emit_glitch(kind);
     }
     
     fn trigger_spatial_tear(&mut self) {
@@ -489,6 +565,7 @@ impl PAL9Neuron {
         }
         
         self.state.reality_integrity -= 0.05;
+        self.emit_glitch("spatial_tear");
     }
     
     fn trigger_warp_gate(&mut self) {
@@ -507,6 +584,7 @@ impl PAL9Neuron {
             
             self.add_message("A Zergling appears! It looks confused.".to_string());
             self.state.reality_integrity -= 0.1;
+            self.emit_glitch("warp_gate");
         }
     }
     
@@ -564,6 +642,63 @@ impl PAL9Neuron {
         self.glitch_accumulator += 10.0;
     }
     
+    fn check_awareness_thresholds(&mut self) {
+        let (previous, current) = (self.last_awareness, self.state.awareness);
+        for &threshold in AWARENESS_THRESHOLDS.iter() {
+            if previous < threshold && current >= threshold {
+                self.emit(GameEvent::AwarenessThreshold {
+                    threshold,
+                    awareness: current,
+                    turn: self.state.turn_count,
+                });
+            }
+        }
+        self.last_awareness = current;
+    }
+    
+    fn apply_inbound(&mut self, action: InboundAction) {
+        match action {
+            InboundAction::SpawnEntity { glyph, name, from_universe, hp } => {
+                if let Some((x, y)) = self.find_empty_floor() {
+                    self.add_message(format!("HAL9 sends a {} from {}.", name, from_universe));
+                    self.state.monsters.push(Monster {
+                        glyph,
+                        x,
+                        y,
+                        hp,
+                        awareness: 0.5,
+                        name,
+                        from_universe,
+                    });
+                }
+            }
+            InboundAction::Message { text } => {
+                self.add_message(format!("A voice from above: '{}'", text));
+            }
+            InboundAction::AdjustReality { delta } => {
+                self.state.reality_integrity = (self.state.reality_integrity + delta).clamp(0.0, 1.0);
+            }
+            InboundAction::AdjustAwareness { delta } => {
+                self.state.awareness = (self.state.awareness + delta).max(0.0);
+            }
+        }
+    }
+    
+    fn emit_glitch(&mut self, kind: &str) {
+        self.emit(GameEvent::RealityGlitch {
+            kind: kind.to_string(),
+            reality_integrity: self.state.reality_integrity,
+            turn: self.state.turn_count,
+        });
+    }
+    
+    fn emit(&mut self, event: GameEvent) {
+        self.events.push(event);
+        if self.events.len() > MAX_PENDING_EVENTS {
+            self.events.remove(0);
+        }
+    }
+    
     fn show_help(&mut self) {
         self.add_message("Commands: hjkl/arrows to move, t to talk, ? for help".to_string());
         self.add_message("Mission: Help Professor Kim debug Universe #1847".to_string());
@@ -4,8 +4,11 @@
 use wasm_bindgen::prelude::*;
 use web_sys::{CanvasRenderingContext2d, HtmlCanvasElement, KeyboardEvent};
 
+pub mod bridge;
 pub mod game;
+use bridge::{BridgeConfig, EventBridge, EventSink};
 use game::PAL9Neuron;
+use hal9_core::NeuronSignal;
 
 // When the `wee_alloc` feature is enabled, use `wee_alloc` as the global allocator
 #[cfg(feature = "wee_alloc")]
@@ -30,6 +33,22 @@ pub struct UltimaOfflinePAL {
     context: CanvasRenderingContext2d,
     cell_width: f64,
     cell_height: f64,
+    bridge: EventBridge,
+}
+
+/// Hands each outbound signal to a JS function as a JSON string
+struct JsCallbackSink {
+    callback: js_sys::Function,
+}
+
+impl EventSink for JsCallbackSink {
+    fn send(&mut self, signal: NeuronSignal) -> Result<(), String> {
+        let json = serde_json::to_string(&signal).map_err(|e| e.to_string())?;
+        self.callback
+            .call1(&JsValue::NULL, &JsValue::from_str(&json))
+            .map(|_| ())
+            .map_err(|e| format!("{:?}", e))
+    }
 }
 
 #[wasm_bindgen]
@@ -70,6 +89,7 @@ impl UltimaOfflinePAL {
             context,
             cell_width,
             cell_height,
+            bridge: EventBridge::disabled(),
         })
     }
     
@@ -92,6 +112,7 @@ impl UltimaOfflinePAL {
         
         if let Some(cmd) = command {
             self.neuron.process_command(cmd);
+            self.publish_events();
             self.render();
         }
     }
@@ -147,6 +168,7 @@ impl UltimaOfflinePAL {
     pub fn tick(&mut self) {
         // Update game state
         self.neuron.think();
+        self.publish_events();
         
         // Check for reality glitches
         if self.neuron.should_glitch() {
@@ -156,6 +178,31 @@ impl UltimaOfflinePAL {
         self.render();
     }
     
+    /// Send game events to `callback` as serialized NeuronSignals.
+    /// Enables the bridge; rate limits to `events_per_second` with bursts of `burst`.
+    #[wasm_bindgen]
+    pub fn set_event_callback(&mut self, callback: js_sys::Function, events_per_second: f64, burst: u32) {
+        self.bridge = EventBridge::new(BridgeConfig {
+            enabled: true,
+            events_per_second,
+            burst,
+            ..Default::default()
+        });
+        self.bridge.set_sink(Box::new(JsCallbackSink { callback }));
+    }
+    
+    #[wasm_bindgen]
+    pub fn set_bridge_enabled(&mut self, enabled: bool) {
+        self.bridge.set_enabled(enabled);
+    }
+    
+    /// Queue a serialized NeuronSignal from HAL9; applied on the next tick
+    #[wasm_bindgen]
+    pub fn inject_signal(&mut self, json: &str) -> Result<(), JsValue> {
+        self.neuron.queue_signal(json)
+            .map_err(|e| JsValue::from_str(&e))
+    }
+    
     #[wasm_bindgen]
     pub fn get_save_data(&self) -> String {
         self.neuron.serialize_state()
@@ -186,6 +233,11 @@ impl UltimaOfflinePAL {
     }
     
     // Private helper methods
+    fn publish_events(&mut self) {
+        let events = self.neuron.drain_events();
+        self.bridge.publish(events, js_sys::Date::now());
+    }
+    
     fn get_color_for_char(&self, ch: char) -> String {
         match ch {
             '@' => "#FFFFFF",  // Player - white
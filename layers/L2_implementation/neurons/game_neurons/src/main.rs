@@ -1,9 +1,6 @@
 // Desktop version of Ultima Offline PAL Edition
 // For testing before WASM deployment
 
-#[cfg(feature = "desktop")]
-mod game;
-
 #[cfg(feature = "desktop")]
 use crossterm::{
     cursor,
@@ -20,7 +17,7 @@ use std::time::Duration;
 
 #[cfg(feature = "desktop")]
 fn main() -> io::Result<()> {
-    use game::PAL9Neuron;
+    use ultima_offline_pal::game::PAL9Neuron;
     
     println!("Ultima Offline PAL Edition v0.001");
     println!("A game aware of its own existence");
@@ -1,6 +1,7 @@
 // Module declarations for Ultima Offline PAL Edition
 
 pub mod game;
+pub mod bridge;
 
 #[cfg(target_arch = "wasm32")]
 pub mod lib;
@@ -0,0 +1,53 @@
+//! API spend and budget periods
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// How often a period budget resets
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BudgetPeriod {
+    Daily,
+    /// Weeks start on Monday
+    Weekly,
+    Monthly,
+}
+
+/// Current period as reported by the costs API
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PeriodStatus {
+    pub period: BudgetPeriod,
+    pub period_start: DateTime<Utc>,
+    pub resets_at: DateTime<Utc>,
+    pub budget: f64,
+    pub carried_over: f64,
+    pub consumed: f64,
+    pub remaining: f64,
+    /// Model requests are downgraded to while the budget is exhausted
+    pub downgraded_to: Option<String>,
+}
+
+/// Cost statistics
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CostStats {
+    pub hourly_cost: f64,
+    pub hourly_tokens: u64,
+    pub daily_cost: f64,
+    pub daily_tokens: u64,
+    pub total_cost: f64,
+    pub hourly_limit: f64,
+    pub daily_limit: f64,
+    pub period: Option<PeriodStatus>,
+    /// Spend of finished chains per layer since the server started
+    #[serde(default)]
+    pub by_layer: Vec<LayerCost>,
+}
+
+/// Spend of one layer
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LayerCost {
+    pub layer: String,
+    pub signals: u64,
+    pub tokens: u64,
+    pub cost: f64,
+}
@@ -0,0 +1,155 @@
+//! Error bodies and the error code taxonomy
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+/// Machine-readable error class reported in [`ErrorDetails::code`]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ErrorCode {
+    BadRequest,
+    InvalidInput,
+    ConfigError,
+    SerializationError,
+    Unauthorized,
+    Forbidden,
+    NotFound,
+    Conflict,
+    RateLimited,
+    NeuronError,
+    RoutingError,
+    ClaudeError,
+    IoError,
+    InternalError,
+    BadGateway,
+    ServiceUnavailable,
+    GatewayTimeout,
+    /// The server is read-only for maintenance
+    Maintenance,
+    /// A code this version does not know
+    Unknown(String),
+}
+
+impl ErrorCode {
+    pub fn as_str(&self) -> &str {
+        match self {
+            ErrorCode::BadRequest => "BAD_REQUEST",
+            ErrorCode::InvalidInput => "INVALID_INPUT",
+            ErrorCode::ConfigError => "CONFIG_ERROR",
+            ErrorCode::SerializationError => "SERIALIZATION_ERROR",
+            ErrorCode::Unauthorized => "UNAUTHORIZED",
+            ErrorCode::Forbidden => "FORBIDDEN",
+            ErrorCode::NotFound => "NOT_FOUND",
+            ErrorCode::Conflict => "CONFLICT",
+            ErrorCode::RateLimited => "RATE_LIMITED",
+            ErrorCode::NeuronError => "NEURON_ERROR",
+            ErrorCode::RoutingError => "ROUTING_ERROR",
+            ErrorCode::ClaudeError => "CLAUDE_ERROR",
+            ErrorCode::IoError => "IO_ERROR",
+            ErrorCode::InternalError => "INTERNAL_ERROR",
+            ErrorCode::BadGateway => "BAD_GATEWAY",
+            ErrorCode::ServiceUnavailable => "SERVICE_UNAVAILABLE",
+            ErrorCode::GatewayTimeout => "GATEWAY_TIMEOUT",
+            ErrorCode::Maintenance => "MAINTENANCE",
+            ErrorCode::Unknown(code) => code,
+        }
+    }
+
+    /// Code for a response that carries none of its own
+    pub fn from_status(status: u16) -> Self {
+        match status {
+            400 => ErrorCode::BadRequest,
+            401 => ErrorCode::Unauthorized,
+            403 => ErrorCode::Forbidden,
+            404 => ErrorCode::NotFound,
+            409 => ErrorCode::Conflict,
+            429 => ErrorCode::RateLimited,
+            500 => ErrorCode::InternalError,
+            502 => ErrorCode::BadGateway,
+            503 => ErrorCode::ServiceUnavailable,
+            504 => ErrorCode::GatewayTimeout,
+            _ => ErrorCode::Unknown("UNKNOWN_ERROR".to_string()),
+        }
+    }
+
+    /// Whether the same request may succeed if sent again later. Matches the
+    /// errors the server itself retries internally, plus load shedding.
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            ErrorCode::RateLimited
+                | ErrorCode::RoutingError
+                | ErrorCode::ClaudeError
+                | ErrorCode::IoError
+                | ErrorCode::InternalError
+                | ErrorCode::BadGateway
+                | ErrorCode::ServiceUnavailable
+                | ErrorCode::GatewayTimeout
+                | ErrorCode::Maintenance
+        )
+    }
+}
+
+impl From<&str> for ErrorCode {
+    fn from(code: &str) -> Self {
+        match code {
+            "BAD_REQUEST" => ErrorCode::BadRequest,
+            "INVALID_INPUT" => ErrorCode::InvalidInput,
+            "CONFIG_ERROR" => ErrorCode::ConfigError,
+            "SERIALIZATION_ERROR" => ErrorCode::SerializationError,
+            "UNAUTHORIZED" => ErrorCode::Unauthorized,
+            "FORBIDDEN" => ErrorCode::Forbidden,
+            "NOT_FOUND" => ErrorCode::NotFound,
+            "CONFLICT" => ErrorCode::Conflict,
+            "RATE_LIMITED" => ErrorCode::RateLimited,
+            "NEURON_ERROR" => ErrorCode::NeuronError,
+            "ROUTING_ERROR" => ErrorCode::RoutingError,
+            "CLAUDE_ERROR" => ErrorCode::ClaudeError,
+            "IO_ERROR" => ErrorCode::IoError,
+            "INTERNAL_ERROR" => ErrorCode::InternalError,
+            "BAD_GATEWAY" => ErrorCode::BadGateway,
+            "SERVICE_UNAVAILABLE" => ErrorCode::ServiceUnavailable,
+            "GATEWAY_TIMEOUT" => ErrorCode::GatewayTimeout,
+            "MAINTENANCE" => ErrorCode::Maintenance,
+            other => ErrorCode::Unknown(other.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Error response format
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+pub struct ErrorResponse {
+    pub error: ErrorDetails,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional, type = "number"))]
+    pub retry_after: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub help_url: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+pub struct ErrorDetails {
+    pub code: String,
+    pub message: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub error_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub details: Option<serde_json::Value>,
+}
+
+impl ErrorDetails {
+    pub fn error_code(&self) -> ErrorCode {
+        ErrorCode::from(self.code.as_str())
+    }
+}
@@ -0,0 +1,90 @@
+//! Events of the signal stream, sent over WebSocket and server-sent events
+
+use serde::{Deserialize, Serialize};
+
+/// Name of the server-sent event reporting events dropped before the client
+/// read them
+pub const LAGGED_EVENT: &str = "lagged";
+
+/// One event of the signal stream
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SignalEvent {
+    SignalUpdate {
+        signal_id: String,
+        neuron_id: String,
+        status: String,
+    },
+    NeuronStateChange {
+        neuron_id: String,
+        old_state: String,
+        new_state: String,
+    },
+    ServerEvent {
+        event: String,
+        details: String,
+    },
+}
+
+impl SignalEvent {
+    /// Value of the `type` field
+    pub fn event_type(&self) -> &'static str {
+        match self {
+            SignalEvent::SignalUpdate { .. } => "signal_update",
+            SignalEvent::NeuronStateChange { .. } => "neuron_state_change",
+            SignalEvent::ServerEvent { .. } => "server_event",
+        }
+    }
+}
+
+/// Query of the stream endpoints; every field set must match
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct StreamFilter {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub neuron_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signal_id: Option<String>,
+    /// Event `type`, e.g. `signal_update`
+    #[serde(default, rename = "type", skip_serializing_if = "Option::is_none")]
+    pub event_type: Option<String>,
+}
+
+impl StreamFilter {
+    /// Whether `event` passes the filter. Server events carry no neuron or
+    /// signal and only pass filters on `type`.
+    pub fn matches(&self, event: &SignalEvent) -> bool {
+        let (neuron_id, signal_id) = match event {
+            SignalEvent::SignalUpdate { signal_id, neuron_id, .. } => (Some(neuron_id), Some(signal_id)),
+            SignalEvent::NeuronStateChange { neuron_id, .. } => (Some(neuron_id), None),
+            SignalEvent::ServerEvent { .. } => (None, None),
+        };
+        let field_matches = |wanted: &Option<String>, actual: Option<&String>| {
+            wanted.as_ref().is_none_or(|wanted| actual == Some(wanted))
+        };
+        field_matches(&self.neuron_id, neuron_id)
+            && field_matches(&self.signal_id, signal_id)
+            && self.event_type.as_deref().is_none_or(|wanted| wanted == event.event_type())
+    }
+
+    /// Query string pairs
+    pub fn to_pairs(&self) -> Vec<(String, String)> {
+        let mut pairs = Vec::new();
+        if let Some(neuron_id) = &self.neuron_id {
+            pairs.push(("neuron_id".to_string(), neuron_id.clone()));
+        }
+        if let Some(signal_id) = &self.signal_id {
+            pairs.push(("signal_id".to_string(), signal_id.clone()));
+        }
+        if let Some(event_type) = &self.event_type {
+            pairs.push(("type".to_string(), event_type.clone()));
+        }
+        pairs
+    }
+}
+
+/// Data of a [`LAGGED_EVENT`]: events that fell out of the server's buffer
+/// before the client read them
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct StreamLagged {
+    pub skipped: u64,
+}
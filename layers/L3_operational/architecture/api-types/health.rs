@@ -0,0 +1,23 @@
+//! Health endpoint body
+
+use serde::{Deserialize, Serialize};
+
+/// Body of `GET /health`, returned with 503 when the server is unhealthy
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HealthSummary {
+    /// `healthy`, `degraded` or `unhealthy`
+    pub status: String,
+    pub service: String,
+    pub version: String,
+    pub timestamp: String,
+    /// Refusing writes for maintenance; absent from older servers
+    #[serde(default)]
+    pub read_only: bool,
+}
+
+impl HealthSummary {
+    /// Whether the server takes traffic; degraded servers still do
+    pub fn is_serving(&self) -> bool {
+        self.status != "unhealthy"
+    }
+}
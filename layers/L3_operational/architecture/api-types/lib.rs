@@ -0,0 +1,106 @@
+//! Request and response types of the HAL9 HTTP API
+//!
+//! Shared by the server and `hal9-client`, so a change to the wire format
+//! breaks the build of both sides instead of failing at runtime. The crate
+//! only depends on serde and chrono and holds no behaviour beyond small
+//! helpers on the types themselves.
+
+pub mod auth;
+pub mod costs;
+pub mod error;
+pub mod events;
+pub mod health;
+pub mod neurons;
+pub mod signals;
+pub mod templates;
+
+pub use auth::{LoginRequest, LoginResponse, RefreshRequest, RefreshResponse, TokenPair, UserResponse};
+pub use costs::{BudgetPeriod, CostStats, LayerCost, PeriodStatus};
+pub use error::{ErrorCode, ErrorDetails, ErrorResponse};
+pub use events::{SignalEvent, StreamFilter, StreamLagged, LAGGED_EVENT};
+pub use health::HealthSummary;
+pub use neurons::{ConcurrencyStats, NeuronChanges, NeuronInfo};
+pub use signals::{
+    ApprovalGate, BatchItemResult, BatchSubmitRequest, BatchSubmitResponse, ChainResult, ChainStatus, CitedMemory,
+    DeadLetter, DeferredBranch, FanOutTruncation, FanOutUsage, FeedbackEdge, GateStatus, NodeCitations,
+    OutputFormat, SubmitSignalRequest, SubmitSignalResponse, ToolLimit, ToolUsage, ToolViolation, TruncationReason,
+};
+pub use templates::{SubmitTemplateRequest, SubmitTemplateResponse, TemplateValue};
+
+use serde::{Deserialize, Serialize};
+
+/// Header carrying a client-chosen key that makes a submission safe to retry
+pub const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+
+/// Envelope of every `/api/v1` JSON response
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiResponse<T> {
+    pub success: bool,
+    pub data: Option<T>,
+    pub error: Option<String>,
+}
+
+impl<T> ApiResponse<T> {
+    pub fn success(data: T) -> Self {
+        Self {
+            success: true,
+            data: Some(data),
+            error: None,
+        }
+    }
+
+    pub fn error(msg: impl Into<String>) -> Self {
+        Self {
+            success: false,
+            data: None,
+            error: Some(msg.into()),
+        }
+    }
+}
+
+/// Standard list envelope
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    /// Pass back as `cursor` for the next page; absent on the last page
+    pub next_cursor: Option<String>,
+    /// Items matching the filters when this page was read
+    pub total_estimate: usize,
+}
+
+/// Query of a list endpoint: `limit`, `cursor`, `sort` and field filters
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ListQuery {
+    pub limit: Option<usize>,
+    pub cursor: Option<String>,
+    /// Field name, prefixed with `-` for descending order
+    pub sort: Option<String>,
+    /// `(field, value)` pairs; a comma-separated value matches any of them
+    pub filters: Vec<(String, String)>,
+}
+
+impl ListQuery {
+    /// Query continuing after `page`, or `None` if it was the last one
+    pub fn next<T>(&self, page: &Page<T>) -> Option<Self> {
+        page.next_cursor.as_ref().map(|cursor| Self {
+            cursor: Some(cursor.clone()),
+            ..self.clone()
+        })
+    }
+
+    /// Query string pairs in the order the server documents them
+    pub fn to_pairs(&self) -> Vec<(String, String)> {
+        let mut pairs = Vec::new();
+        if let Some(limit) = self.limit {
+            pairs.push(("limit".to_string(), limit.to_string()));
+        }
+        if let Some(cursor) = &self.cursor {
+            pairs.push(("cursor".to_string(), cursor.clone()));
+        }
+        if let Some(sort) = &self.sort {
+            pairs.push(("sort".to_string(), sort.clone()));
+        }
+        pairs.extend(self.filters.iter().cloned());
+        pairs
+    }
+}
@@ -0,0 +1,56 @@
+//! Neuron listings
+
+use serde::{Deserialize, Serialize};
+
+/// Current load and overflow counts of one neuron
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ConcurrencyStats {
+    pub max_concurrent: usize,
+    pub max_queued: usize,
+    /// Signals being processed
+    pub in_flight: usize,
+    /// Signals admitted and waiting for a processing slot
+    pub queued: usize,
+    pub rejected: u64,
+    pub shed: u64,
+    /// Signals that found the queue full and waited for room
+    pub blocked: u64,
+}
+
+/// Neuron information
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NeuronInfo {
+    pub id: String,
+    pub layer: String,
+    pub state: String,
+    pub is_healthy: bool,
+    pub concurrency: ConcurrencyStats,
+    /// State of the circuit breaker on its provider calls: `closed`, `open`
+    /// or `half_open`
+    #[serde(default)]
+    pub circuit_breaker: String,
+}
+
+/// Neurons that changed since a version of the server's neuron change feed
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct NeuronChanges {
+    /// Version to ask for changes since next time
+    pub version: u64,
+    /// Whether `changed` lists every neuron, because no version or one the
+    /// server no longer knows was asked for; neurons not listed are gone
+    #[serde(default)]
+    pub full: bool,
+    /// Neurons added or changed, as they are now
+    #[serde(default)]
+    pub changed: Vec<NeuronInfo>,
+    /// IDs of neurons removed
+    #[serde(default)]
+    pub removed: Vec<String>,
+}
+
+impl NeuronChanges {
+    /// Whether nothing changed since the version asked for
+    pub fn is_empty(&self) -> bool {
+        !self.full && self.changed.is_empty() && self.removed.is_empty()
+    }
+}
@@ -0,0 +1,420 @@
+//! Signal submission, chain results and dead letters
+
+use std::collections::{BTreeMap, HashMap};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Signal submission request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubmitSignalRequest {
+    pub content: String,
+    pub layer: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub neuron_id: Option<String>,
+    /// Namespaced signal metadata, validated against the metadata schema
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub metadata: HashMap<String, String>,
+    /// Format the chain's final output should be returned in
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_format: Option<OutputFormat>,
+    /// JSON Schema a `json` output must match
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_schema: Option<serde_json::Value>,
+    /// Cost attribution tags, e.g. `{"project": "checkout"}`, carried by
+    /// every signal of the chain
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub tags: BTreeMap<String, String>,
+    /// Session the chain is the next turn of; its prompt carries the
+    /// conversation so far
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+}
+
+impl SubmitSignalRequest {
+    pub fn new(layer: impl Into<String>, content: impl Into<String>) -> Self {
+        Self {
+            content: content.into(),
+            layer: layer.into(),
+            neuron_id: None,
+            metadata: HashMap::new(),
+            output_format: None,
+            output_schema: None,
+            tags: BTreeMap::new(),
+            session_id: None,
+        }
+    }
+}
+
+/// Shape of a chain's final output
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+pub enum OutputFormat {
+    Markdown,
+    Json,
+    Text,
+}
+
+impl OutputFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OutputFormat::Markdown => "markdown",
+            OutputFormat::Json => "json",
+            OutputFormat::Text => "text",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "markdown" => Some(OutputFormat::Markdown),
+            "json" => Some(OutputFormat::Json),
+            "text" => Some(OutputFormat::Text),
+            _ => None,
+        }
+    }
+}
+
+/// Accepted signal; the root signal's ID doubles as the chain ID
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SubmitSignalResponse {
+    pub signal_id: String,
+    pub chain_id: String,
+    pub message: String,
+}
+
+/// Several signals submitted in one request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchSubmitRequest {
+    pub signals: Vec<SubmitSignalRequest>,
+}
+
+/// Outcome of one signal of a batch
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BatchItemResult {
+    /// Position of the signal in the request
+    pub index: usize,
+    pub signal_id: Option<String>,
+    pub error: Option<String>,
+}
+
+/// Per-signal outcomes, in request order. A failing signal does not stop
+/// the others from being submitted.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BatchSubmitResponse {
+    pub results: Vec<BatchItemResult>,
+    pub accepted: usize,
+    pub rejected: usize,
+}
+
+/// Chain lifecycle status
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+pub enum ChainStatus {
+    Running,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+/// Aggregated view of a chain's results
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+pub struct ChainResult {
+    pub chain_id: String,
+    pub status: ChainStatus,
+    pub input: String,
+    pub steps_completed: usize,
+    pub pending_signals: usize,
+    pub layers: Vec<String>,
+    /// Output of the deepest layer a forward signal reached, if any step
+    /// succeeded
+    pub final_output: Option<String>,
+    /// Format requested for the final output
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub output_format: Option<OutputFormat>,
+    /// The final output did not conform to the requested format, even after
+    /// reformatting, and is returned as it was
+    #[serde(default)]
+    pub format_degraded: bool,
+    /// Why the final output failed its format check, when it did
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub format_reason: Option<String>,
+    pub errors: Vec<String>,
+    /// Backward (gradient) signals processed within the chain
+    #[serde(default)]
+    pub backward_steps: usize,
+    /// The gradients those signals carried, in the order they were processed
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[cfg_attr(feature = "ts", ts(as = "Option<Vec<FeedbackEdge>>", optional))]
+    pub feedback: Vec<FeedbackEdge>,
+    /// Chain this one replays; compare the two with
+    /// `/api/v1/chains/compare?a={replay_of}&b={chain_id}`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub replay_of: Option<String>,
+    /// Cost attribution tags the chain was submitted with
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    #[cfg_attr(feature = "ts", ts(as = "Option<BTreeMap<String, String>>", optional))]
+    pub tags: BTreeMap<String, String>,
+    /// Node budget consumption and dropped branches, when a fan-out limit
+    /// applied
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub fan_out: Option<FanOutUsage>,
+    /// Tool calls against the chain's sandbox limits, once the chain made
+    /// one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub tools: Option<ToolUsage>,
+    /// Memory entries each step's output cited, in the order the steps
+    /// were processed; steps that cited none are left out
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[cfg_attr(feature = "ts", ts(as = "Option<Vec<NodeCitations>>", optional))]
+    pub cited_memories: Vec<NodeCitations>,
+    /// Branches parked while every provider was down, in the order they
+    /// were parked; the chain keeps running until they resume
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[cfg_attr(feature = "ts", ts(as = "Option<Vec<DeferredBranch>>", optional))]
+    pub deferred: Vec<DeferredBranch>,
+    /// Outputs held for approval, in the order they were held; the chain
+    /// keeps running while one is pending
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[cfg_attr(feature = "ts", ts(as = "Option<Vec<ApprovalGate>>", optional))]
+    pub gates: Vec<ApprovalGate>,
+    #[cfg_attr(feature = "ts", ts(type = "number"))]
+    pub prompt_tokens: u64,
+    #[cfg_attr(feature = "ts", ts(type = "number"))]
+    pub completion_tokens: u64,
+    pub created_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+    #[cfg_attr(feature = "ts", ts(type = "number | null"))]
+    pub duration_ms: Option<i64>,
+    /// The chain's payloads were moved to the archive; served from its
+    /// summary or read back from the archive files
+    #[serde(default)]
+    pub archived: bool,
+}
+
+/// A backward signal carrying an error from one neuron back to another
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+pub struct FeedbackEdge {
+    pub from_neuron: String,
+    pub to_neuron: String,
+    /// Kind of error reported, e.g. `timeout`
+    pub error_class: String,
+    pub magnitude: f64,
+    /// Factor the magnitude was scaled by when the signal was sent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub attenuation: Option<f64>,
+}
+
+/// A memory entry a neuron's output cited
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+pub struct CitedMemory {
+    /// Reference id the entry was given in the prompt, e.g. `mem-1a2b3c4d`
+    pub reference: String,
+    pub memory_id: String,
+    /// Namespace the entry belongs to
+    pub namespace: String,
+}
+
+/// Memory entries the output of one step of a chain cited
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+pub struct NodeCitations {
+    pub signal_id: String,
+    pub neuron_id: String,
+    pub layer: String,
+    pub memories: Vec<CitedMemory>,
+}
+
+/// A branch of a chain parked during a provider outage
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+pub struct DeferredBranch {
+    /// Signal that was parked
+    pub signal_id: String,
+    pub neuron_id: String,
+    pub layer: String,
+    /// Why it was parked, e.g. `deferred: provider outage`
+    pub reason: String,
+    pub deferred_at: DateTime<Utc>,
+    /// When it was processed after providers recovered; `None` while parked
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub resumed_at: Option<DateTime<Utc>>,
+}
+
+/// Where an approval gate stands
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+pub enum GateStatus {
+    Pending,
+    Approved,
+    Rejected,
+}
+
+/// A step of a chain whose output was held for a human to approve
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+pub struct ApprovalGate {
+    /// Approval the gate is decided through, see `GET /api/v1/approvals`
+    pub approval_id: String,
+    /// Signal whose output was held
+    pub signal_id: String,
+    /// Signal that produced it; `None` for the chain root
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub parent_id: Option<String>,
+    pub neuron_id: String,
+    pub layer: String,
+    /// Why the output needs approval
+    pub reason: String,
+    pub status: GateStatus,
+    /// Who decided; `timeout` when the configured timeout action was taken
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub decided_by: Option<String>,
+    /// The approver amended the output before it continued
+    #[serde(default)]
+    pub edited: bool,
+    pub requested_at: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub decided_at: Option<DateTime<Utc>>,
+}
+
+/// Fan-out of a chain against its limits
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+pub struct FanOutUsage {
+    /// Signals the chain may process, its root included
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional, type = "number"))]
+    pub node_budget: Option<u64>,
+    /// Forward signals processed so far
+    pub nodes: usize,
+    /// Branches dropped across the chain
+    pub truncated_branches: usize,
+    /// Responses that asked for more branches than allowed
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[cfg_attr(feature = "ts", ts(as = "Option<Vec<FanOutTruncation>>", optional))]
+    pub truncations: Vec<FanOutTruncation>,
+}
+
+/// Why branches of a response were dropped
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+pub enum TruncationReason {
+    /// The layer's maximum children per signal
+    MaxChildren,
+    /// What is left of the chain's node budget
+    NodeBudget,
+}
+
+/// A response that asked for more branches than its limits allowed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+pub struct FanOutTruncation {
+    /// Signal the response answered
+    pub signal_id: String,
+    pub neuron_id: String,
+    pub layer: String,
+    /// Branches the response forwarded to
+    pub requested: usize,
+    /// Branches the limit allowed
+    pub allowed: usize,
+    pub reason: TruncationReason,
+    /// In strict mode the chain failed instead and no branch was kept
+    #[serde(default)]
+    pub failed: bool,
+}
+
+/// Tool calls of a chain against its sandbox limits, counted across all its
+/// branches
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+pub struct ToolUsage {
+    /// Tool calls made
+    #[cfg_attr(feature = "ts", ts(type = "number"))]
+    pub invocations: u64,
+    /// Bytes of parameters sent to tools
+    #[cfg_attr(feature = "ts", ts(type = "number"))]
+    pub outbound_bytes: u64,
+    /// Distinct domains of the URLs tools were called with, sorted
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[cfg_attr(feature = "ts", ts(as = "Option<Vec<String>>", optional))]
+    pub domains: Vec<String>,
+    /// Milliseconds spent waiting on tool calls
+    #[cfg_attr(feature = "ts", ts(type = "number"))]
+    pub tool_time_ms: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional, type = "number"))]
+    pub max_invocations: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional, type = "number"))]
+    pub max_outbound_bytes: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional))]
+    pub max_domains: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "ts", ts(optional, type = "number"))]
+    pub max_tool_time_ms: Option<u64>,
+    /// Tool calls refused for a limit, in the order they were refused
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[cfg_attr(feature = "ts", ts(as = "Option<Vec<ToolViolation>>", optional))]
+    pub violations: Vec<ToolViolation>,
+}
+
+/// A limit on the tool calls of a chain
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+pub enum ToolLimit {
+    Invocations,
+    OutboundBytes,
+    Domains,
+    ToolTime,
+}
+
+impl ToolLimit {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ToolLimit::Invocations => "invocations",
+            ToolLimit::OutboundBytes => "outbound_bytes",
+            ToolLimit::Domains => "domains",
+            ToolLimit::ToolTime => "tool_time",
+        }
+    }
+}
+
+/// A tool call refused, or cut short, for a limit of its chain
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "ts", derive(ts_rs::TS))]
+pub struct ToolViolation {
+    /// Signal whose processing made the call
+    pub signal_id: String,
+    pub neuron_id: String,
+    pub tool: String,
+    pub limit: ToolLimit,
+    pub at: DateTime<Utc>,
+}
+
+/// A signal no neuron would take, as listed by the dead letter API. The
+/// signal is left as JSON so clients don't need the neuron crate's types.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeadLetter {
+    pub neuron_id: String,
+    pub reason: String,
+    pub timestamp: DateTime<Utc>,
+    pub signal: serde_json::Value,
+}
@@ -0,0 +1,38 @@
+//! Submission of chain templates
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+/// Value given for a template variable
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum TemplateValue {
+    /// For string and enum variables
+    Text(String),
+    /// For file variables: the file's name and its text
+    File { file: String, content: String },
+}
+
+/// Variable values a template is rendered with
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SubmitTemplateRequest {
+    #[serde(default)]
+    pub variables: HashMap<String, TemplateValue>,
+    /// Version to render; the latest when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<u32>,
+}
+
+/// Chain started from a template
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SubmitTemplateResponse {
+    pub signal_id: String,
+    pub chain_id: String,
+    pub template: String,
+    /// Version the content was rendered from
+    pub version: u32,
+    /// Artifacts stored for files too large to inline, in variable order
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub artifacts: Vec<String>,
+}
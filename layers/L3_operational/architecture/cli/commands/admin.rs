@@ -0,0 +1,46 @@
+//! Server maintenance
+
+use anyhow::Result;
+
+use crate::output::{self, ApiResponse, CliError, CompactResult, CompactedDatabase, OutputFormat, TieringStatus, TieringStatusResult};
+use crate::target::Target;
+
+/// Compact the server's SQLite databases
+///
+/// Exit codes: 0 ok, 3 server unreachable, 4 compaction failed
+pub async fn compact(target: Target, format: OutputFormat) -> Result<()> {
+    let client = target.client()?;
+
+    let url = target.url("/api/v1/admin/compact");
+    let response = client.post(&url).send().await
+        .map_err(|e| CliError::unreachable(&target.server, e))?;
+
+    let status = response.status();
+    if !status.is_success() {
+        let error_text = response.text().await.unwrap_or_default();
+        return Err(CliError::server(format!("Failed to compact databases ({}): {}", status, error_text)).into());
+    }
+
+    let databases = response.json::<ApiResponse<Vec<CompactedDatabase>>>().await?.into_data()?;
+    output::emit(format, &CompactResult { server: target.server, databases })
+}
+
+/// Show the size of each memory tier
+///
+/// Exit codes: 0 ok, 3 server unreachable, 4 memory disabled or unreadable
+pub async fn tiering_status(target: Target, format: OutputFormat) -> Result<()> {
+    let client = target.client()?;
+
+    let url = target.url("/api/v1/admin/tiering");
+    let response = client.get(&url).send().await
+        .map_err(|e| CliError::unreachable(&target.server, e))?;
+
+    let status = response.status();
+    if !status.is_success() {
+        let error_text = response.text().await.unwrap_or_default();
+        return Err(CliError::server(format!("Failed to get memory tiers ({}): {}", status, error_text)).into());
+    }
+
+    let tiers = response.json::<ApiResponse<TieringStatus>>().await?.into_data()?;
+    output::emit(format, &TieringStatusResult { server: target.server, tiers })
+}
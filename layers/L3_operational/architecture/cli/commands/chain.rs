@@ -0,0 +1,92 @@
+//! Starting chains, in sessions or not, replaying them and comparing them
+//! with their source
+
+use anyhow::Result;
+use serde_json::json;
+
+use crate::output::{self, ApiResponse, ChainComparisonReport, ChainStarted, CliError, OutputFormat, ReplayResult, SessionInfo};
+use crate::target::Target;
+
+/// Start a chain with `content`, as the next turn of `session` if given.
+/// A session of "new" is created first.
+///
+/// Exit codes: 0 ok, 3 server unreachable, 4 session unknown or submission rejected
+pub async fn start(
+    target: Target,
+    content: String,
+    neuron: Option<String>,
+    layer: String,
+    session: Option<String>,
+    format: OutputFormat,
+) -> Result<()> {
+    let client = target.client()?;
+
+    let session = match session.as_deref() {
+        Some("new") => {
+            let url = target.url("/api/v1/sessions");
+            let response = client.post(&url).send().await
+                .map_err(|e| CliError::unreachable(&target.server, e))?;
+            let status = response.status();
+            if !status.is_success() {
+                let error_text = response.text().await.unwrap_or_default();
+                return Err(CliError::server(format!("Failed to start a session ({}): {}", status, error_text)).into());
+            }
+            Some(response.json::<ApiResponse<SessionInfo>>().await?.into_data()?.id)
+        }
+        _ => session,
+    };
+
+    let url = target.url("/api/v1/signal");
+    let payload = json!({ "content": content, "layer": layer, "neuron_id": neuron, "session_id": session });
+    let response = client.post(&url).json(&payload).send().await
+        .map_err(|e| CliError::unreachable(&target.server, e))?;
+
+    let status = response.status();
+    if !status.is_success() {
+        let error_text = response.text().await.unwrap_or_default();
+        return Err(CliError::server(format!("Failed to start chain ({}): {}", status, error_text)).into());
+    }
+
+    let started = response.json::<ApiResponse<ChainStarted>>().await?.into_data()?;
+    output::emit(format, &ChainStarted { session_id: session, ..started })
+}
+
+/// Start a chain again, optionally with a new input
+///
+/// Exit codes: 0 ok, 3 server unreachable, 4 chain unknown or replay rejected
+pub async fn replay(target: Target, chain: String, content: Option<String>, format: OutputFormat) -> Result<()> {
+    let client = target.client()?;
+
+    let url = target.url(&format!("/api/v1/chains/{}/replay", chain));
+    let response = client.post(&url).json(&json!({ "content": content })).send().await
+        .map_err(|e| CliError::unreachable(&target.server, e))?;
+
+    let status = response.status();
+    if !status.is_success() {
+        let error_text = response.text().await.unwrap_or_default();
+        return Err(CliError::server(format!("Failed to replay chain ({}): {}", status, error_text)).into());
+    }
+
+    let result = response.json::<ApiResponse<ReplayResult>>().await?.into_data()?;
+    output::emit(format, &result)
+}
+
+/// Show how chain `b` differs from chain `a`
+///
+/// Exit codes: 0 ok, 3 server unreachable, 4 chain unknown
+pub async fn compare(target: Target, a: String, b: String, format: OutputFormat) -> Result<()> {
+    let client = target.client()?;
+
+    let url = target.url("/api/v1/chains/compare");
+    let response = client.get(&url).query(&[("a", a), ("b", b)]).send().await
+        .map_err(|e| CliError::unreachable(&target.server, e))?;
+
+    let status = response.status();
+    if !status.is_success() {
+        let error_text = response.text().await.unwrap_or_default();
+        return Err(CliError::server(format!("Failed to compare chains ({}): {}", status, error_text)).into());
+    }
+
+    let report = response.json::<ApiResponse<ChainComparisonReport>>().await?.into_data()?;
+    output::emit(format, &report)
+}
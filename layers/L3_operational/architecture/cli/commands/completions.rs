@@ -0,0 +1,18 @@
+//! Completions command implementation
+
+use anyhow::Result;
+use clap::Command;
+use clap_complete::Shell;
+use std::io::Write;
+
+/// Write the completion script for `shell` to `out`
+pub fn generate(shell: Shell, command: &mut Command, out: &mut dyn Write) {
+    let name = command.get_name().to_string();
+    clap_complete::generate(shell, command, name, out);
+}
+
+/// Exit codes: 0 ok, 2 unknown shell
+pub async fn execute(shell: Shell, mut command: Command) -> Result<()> {
+    generate(shell, &mut command, &mut std::io::stdout());
+    Ok(())
+}
@@ -0,0 +1,53 @@
+//! Database command implementation
+
+use anyhow::{Context, Result};
+use std::path::PathBuf;
+
+use crate::output::{self, CliError, ExitCode, OutputFormat, PendingMigration, SchemaReport};
+
+use hal9_core::secrets::EnvKeyProvider;
+use hal9_core::ServerConfig;
+use hal9_server::database_migrations::{self, SchemaError};
+
+/// Show the configured database's schema version against the one this
+/// release expects, without changing it
+///
+/// Exit codes: 0 current or behind, 1 unreadable config or database,
+/// 4 migrated by a newer release or a migration failed partway
+pub async fn status(config_path: PathBuf, format: OutputFormat) -> Result<()> {
+    let config_str = std::fs::read_to_string(&config_path)
+        .context("Failed to read configuration file")?;
+    let mut config: ServerConfig = if config_path.extension().unwrap_or_default() == "yaml" {
+        serde_yaml::from_str(&config_str)
+            .context("Failed to parse YAML configuration")?
+    } else {
+        serde_json::from_str(&config_str)
+            .context("Failed to parse JSON configuration")?
+    };
+    config.decrypt_secrets(&EnvKeyProvider)
+        .context("Failed to decrypt configuration secrets")?;
+
+    let pool = database_migrations::connect(&config.database)
+        .await?
+        .context("No database.url in the configuration")?;
+    let status = database_migrations::status(&pool).await;
+    pool.close().await;
+    let status = status?;
+
+    output::emit(format, &SchemaReport {
+        database: status.database.clone(),
+        state: serde_json::to_value(status.state)?.as_str().unwrap_or_default().to_string(),
+        current: status.current,
+        expected: status.expected,
+        pending: status.pending.iter()
+            .map(|m| PendingMigration { version: m.version, description: m.description.clone() })
+            .collect(),
+        unknown: status.unknown.clone(),
+    })?;
+
+    // Pending migrations are applied at the next start
+    match status.check() {
+        Ok(()) | Err(SchemaError::Behind { .. }) => Ok(()),
+        Err(e) => Err(CliError::new(ExitCode::ServerError, e.to_string()).into()),
+    }
+}
@@ -0,0 +1,68 @@
+//! Dev command implementation
+
+use anyhow::{Context, Result};
+use std::path::PathBuf;
+use std::time::Duration;
+
+use hal9_server::api;
+use hal9_server::dev::{self, DevOptions};
+
+use crate::output::{self, DevReady, DevScenario, DevUser, OutputFormat};
+
+/// How long the scenario's chain may take
+const SCENARIO_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Exit codes: 0 ok, 1 failed start or scenario
+pub async fn execute(scenario: Option<String>, topology: PathBuf, port: u16, format: OutputFormat) -> Result<()> {
+    let options = DevOptions { topology: Some(topology.clone()), scenario };
+    let stack = dev::boot(&options).await.context("Failed to start the dev server")?;
+
+    let url = format!("http://127.0.0.1:{}", port);
+    let listener = tokio::net::TcpListener::bind(("127.0.0.1", port))
+        .await
+        .with_context(|| format!("Failed to listen on port {}", port))?;
+    let router = api::create_api_router(stack.server.clone());
+    let http = tokio::spawn(async move {
+        if let Err(e) = axum::serve(listener, router).await {
+            tracing::error!("HTTP server error: {}", e);
+        }
+    });
+    let watcher = dev::watch_topology(stack.server.clone(), topology.clone(), dev::TOPOLOGY_POLL_INTERVAL);
+
+    let scenario = match stack.scenario {
+        Some((name, _)) => {
+            let result = stack.run_scenario(SCENARIO_TIMEOUT).await
+                .with_context(|| format!("Scenario {} failed", name))?;
+            Some(DevScenario {
+                name: name.to_string(),
+                chain_id: result.chain_id,
+                status: serde_json::to_value(result.status)?.as_str().unwrap_or_default().to_string(),
+                layers: result.layers,
+                final_output: result.final_output,
+            })
+        }
+        None => None,
+    };
+
+    output::emit(format, &DevReady {
+        server_id: dev::DEV_SERVER_ID.to_string(),
+        admin_ui: dev::ADMIN_UI.then(|| format!("{}/admin", url)),
+        url,
+        topology: topology.display().to_string(),
+        neurons: stack.server.topology().len(),
+        users: stack.users.iter().map(|user| DevUser {
+            username: user.username.clone(),
+            role: user.role.clone(),
+            password: user.password.clone(),
+            api_key: user.api_key.clone(),
+        }).collect(),
+        scenario,
+    })?;
+
+    tokio::signal::ctrl_c().await?;
+    tracing::info!("Shutting down...");
+    watcher.abort();
+    http.abort();
+    stack.shutdown().await?;
+    Ok(())
+}
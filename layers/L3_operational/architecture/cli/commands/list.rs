@@ -0,0 +1,101 @@
+//! List command implementation
+
+use anyhow::Result;
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::output::{self, CliError, ListReport, OutputFormat};
+use crate::target::Target;
+
+/// Standard list envelope returned by list endpoints
+#[derive(Debug, Deserialize)]
+struct Page {
+    items: Vec<Value>,
+    next_cursor: Option<String>,
+    total_estimate: usize,
+}
+
+/// Endpoint path for a listable resource
+fn resource_path(resource: &str, webhook: Option<&str>) -> Result<String, CliError> {
+    match resource {
+        "neurons" => Ok("/api/v1/neurons".to_string()),
+        "scaling-events" => Ok("/api/v1/scaling/events".to_string()),
+        "webhooks" => Ok("/api/v1/webhooks".to_string()),
+        "deliveries" => webhook
+            .map(|id| format!("/api/v1/webhooks/{}/deliveries", id))
+            .ok_or_else(|| CliError::usage("--webhook is required to list deliveries")),
+        "errors" => Ok("/api/v1/errors/recent".to_string()),
+        other => Err(CliError::usage(format!(
+            "Unknown resource '{}'; expected neurons, scaling-events, webhooks, deliveries or errors",
+            other
+        ))),
+    }
+}
+
+/// Fetch one page; accepts both the bare envelope and one wrapped in `data`
+async fn fetch_page(client: &reqwest::Client, target: &Target, url: &str, params: &[(String, String)]) -> Result<Page> {
+    let response = client.get(url).query(params).send().await
+        .map_err(|e| CliError::unreachable(&target.server, e))?;
+    let status = response.status();
+    let body: Value = response.json().await?;
+    
+    if !status.is_success() {
+        let message = body.get("error").and_then(Value::as_str).unwrap_or("request failed");
+        return Err(CliError::server(format!("{}: {}", status, message)).into());
+    }
+    
+    let page = match body.get("data") {
+        Some(data) => data.clone(),
+        None => body,
+    };
+    Ok(serde_json::from_value(page)?)
+}
+
+/// Exit codes: 0 ok, 2 invalid resource or filter, 3 server unreachable, 4 server error
+#[allow(clippy::too_many_arguments)]
+pub async fn execute(
+    resource: String,
+    webhook: Option<String>,
+    filters: Vec<String>,
+    sort: Option<String>,
+    limit: Option<usize>,
+    all: bool,
+    target: Target,
+    format: OutputFormat,
+) -> Result<()> {
+    let url = target.url(&resource_path(&resource, webhook.as_deref())?);
+    let client = target.client()?;
+    
+    let mut params = Vec::new();
+    for filter in &filters {
+        let (field, value) = filter.split_once('=')
+            .ok_or_else(|| CliError::usage(format!("Filters take the form field=value, got '{}'", filter)))?;
+        params.push((field.to_string(), value.to_string()));
+    }
+    if let Some(sort) = sort {
+        params.push(("sort".to_string(), sort));
+    }
+    if let Some(limit) = limit {
+        params.push(("limit".to_string(), limit.to_string()));
+    }
+    
+    // Follow cursors until the last page when --all is given
+    let mut items = Vec::new();
+    let mut cursor: Option<String> = None;
+    let (next_cursor, total_estimate) = loop {
+        let mut page_params = params.clone();
+        if let Some(cursor) = &cursor {
+            page_params.push(("cursor".to_string(), cursor.clone()));
+        }
+        
+        let page = fetch_page(&client, &target, &url, &page_params).await?;
+        items.extend(page.items);
+        
+        match page.next_cursor {
+            Some(next) if all => cursor = Some(next),
+            next => break (next, page.total_estimate),
+        }
+    };
+    
+    output::emit(format, &ListReport { resource, items, total_estimate, next_cursor })
+}
@@ -0,0 +1,37 @@
+//! Logs command implementation
+
+use anyhow::Result;
+
+use crate::output::{self, ApiResponse, CliError, LogEntry, LogsReport, OutputFormat};
+use crate::target::Target;
+
+/// Exit codes: 0 ok, 3 server unreachable, 4 server error
+pub async fn execute(
+    target: Target,
+    chain: Option<String>,
+    level: Option<String>,
+    since: String,
+    limit: usize,
+    format: OutputFormat,
+) -> Result<()> {
+    let client = target.client()?;
+    
+    let mut params = vec![("since", since), ("limit", limit.to_string())];
+    if let Some(chain) = chain {
+        params.push(("chain_id", chain));
+    }
+    if let Some(level) = level {
+        params.push(("level", level));
+    }
+    
+    let url = target.url("/api/v1/logs");
+    let response = client.get(&url).query(&params).send().await
+        .map_err(|e| CliError::unreachable(&target.server, e))?;
+    
+    if !response.status().is_success() {
+        return Err(CliError::server(format!("Failed to query logs: {}", response.status())).into());
+    }
+    
+    let entries = response.json::<ApiResponse<Vec<LogEntry>>>().await?.into_data()?;
+    output::emit(format, &LogsReport { entries })
+}
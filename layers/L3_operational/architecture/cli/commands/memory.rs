@@ -0,0 +1,85 @@
+//! Memory export and import, for promoting memories between environments
+
+use anyhow::{Context, Result};
+use std::io::Write;
+use std::path::PathBuf;
+
+use crate::output::{self, ApiResponse, CliError, MemoryExportResult, MemoryImportReport, OutputFormat};
+use crate::target::Target;
+
+/// Write a dump to `file`, or to stdout without one
+///
+/// Exit codes: 0 ok, 1 unwritable file, 3 server unreachable, 4 server error
+pub async fn export(
+    target: Target,
+    namespace: Option<String>,
+    neuron: Option<String>,
+    file: Option<PathBuf>,
+    format: OutputFormat,
+) -> Result<()> {
+    let client = target.client()?;
+
+    let mut params = Vec::new();
+    if let Some(namespace) = namespace {
+        params.push(("namespace", namespace));
+    }
+    if let Some(neuron) = neuron {
+        params.push(("neuron", neuron));
+    }
+
+    let url = target.url("/api/v1/memory/export");
+    let response = client.get(&url).query(&params).send().await
+        .map_err(|e| CliError::unreachable(&target.server, e))?;
+
+    let status = response.status();
+    if !status.is_success() {
+        let error_text = response.text().await.unwrap_or_default();
+        return Err(CliError::server(format!("Failed to export memory ({}): {}", status, error_text)).into());
+    }
+
+    let dump = response.bytes().await?;
+    let Some(file) = file else {
+        // The dump itself is the output
+        std::io::stdout().write_all(&dump)?;
+        return Ok(());
+    };
+
+    std::fs::write(&file, &dump)
+        .with_context(|| format!("Failed to write {}", file.display()))?;
+    // The header line is not an entry
+    let entries = dump.split(|byte| *byte == b'\n').filter(|line| !line.is_empty()).count().saturating_sub(1);
+    output::emit(format, &MemoryExportResult { file: file.display().to_string(), entries })
+}
+
+/// Exit codes: 0 ok, 1 unreadable file, 3 server unreachable, 4 dump rejected
+pub async fn import(
+    target: Target,
+    file: PathBuf,
+    policy: String,
+    dry_run: bool,
+    recompute_embeddings: bool,
+    format: OutputFormat,
+) -> Result<()> {
+    let dump = std::fs::read(&file)
+        .with_context(|| format!("Failed to read {}", file.display()))?;
+    let client = target.client()?;
+
+    let params = [
+        ("policy", policy),
+        ("dry_run", dry_run.to_string()),
+        ("recompute_embeddings", recompute_embeddings.to_string()),
+    ];
+
+    let url = target.url("/api/v1/memory/import");
+    let response = client.post(&url).query(&params).body(dump).send().await
+        .map_err(|e| CliError::unreachable(&target.server, e))?;
+
+    let status = response.status();
+    if !status.is_success() {
+        let error_text = response.text().await.unwrap_or_default();
+        return Err(CliError::server(format!("Failed to import memory ({}): {}", status, error_text)).into());
+    }
+
+    let report = response.json::<ApiResponse<MemoryImportReport>>().await?.into_data()?;
+    output::emit(format, &report)
+}
@@ -1,6 +1,17 @@
 //! CLI command implementations
 
 pub mod start;
+pub mod dev;
 pub mod status;
 pub mod signal;
-pub mod stop;
\ No newline at end of file
+pub mod submit;
+pub mod stop;
+pub mod secrets;
+pub mod logs;
+pub mod list;
+pub mod memory;
+pub mod chain;
+pub mod admin;
+pub mod completions;
+pub mod db;
+pub mod profile;
\ No newline at end of file
@@ -0,0 +1,127 @@
+//! Named server profiles in ~/.config/hal9/config.toml
+
+use anyhow::Result;
+use clap::ValueEnum;
+use hal9_client::profile::{self, ApiKeyRef, Profile, ProfileFile};
+use std::path::{Path, PathBuf};
+
+use crate::output::{self, CliError, ExitCode, OutputFormat, ProfileChange, ProfileEntry, ProfileList};
+
+/// Settings of a profile being added
+pub struct NewProfile {
+    pub server: String,
+    pub api_key_env: Option<String>,
+    pub api_key_keyring: Option<String>,
+    pub output: Option<OutputFormat>,
+    pub org: Option<String>,
+    pub production: bool,
+}
+
+fn config_path() -> Result<PathBuf, CliError> {
+    profile::default_path()
+        .ok_or_else(|| CliError::new(ExitCode::Failure, "Cannot locate the profile file: HOME is not set"))
+}
+
+fn load(path: &Path) -> Result<ProfileFile, CliError> {
+    ProfileFile::load(path).map_err(|e| CliError::new(ExitCode::Failure, e.to_string()))
+}
+
+fn save(file: &ProfileFile, path: &Path) -> Result<(), CliError> {
+    file.save(path).map_err(|e| CliError::new(ExitCode::Failure, e.to_string()))
+}
+
+fn unknown(name: &str) -> CliError {
+    CliError::usage(format!("Unknown profile '{}'; see `hal9 profile list`", name))
+}
+
+/// Where a profile's API key is looked up, without the key itself
+fn describe_key(key: &ApiKeyRef) -> String {
+    let mut sources = Vec::new();
+    if let Some(entry) = &key.keyring {
+        sources.push(format!("keyring:{}", entry));
+    }
+    if let Some(var) = &key.env {
+        sources.push(format!("env:{}", var));
+    }
+    if key.value.is_some() {
+        sources.push("plaintext".to_string());
+    }
+    sources.join(", ")
+}
+
+/// List profiles, marking the one `selected` by `--profile`, HAL9_PROFILE
+/// or the default
+///
+/// Exit codes: 0 ok, 1 unreadable profile file
+pub async fn list(selected: Option<String>, format: OutputFormat) -> Result<()> {
+    let path = config_path()?;
+    let file = load(&path)?;
+    let active = file.selected(selected.as_deref(), &|name| std::env::var(name).ok());
+    let profiles = file.profiles.into_iter()
+        .map(|(name, profile)| ProfileEntry {
+            name,
+            server: profile.server,
+            api_key: profile.api_key.as_ref().map(describe_key),
+            output: profile.output,
+            org: profile.org,
+            production: profile.production,
+        })
+        .collect();
+    output::emit(format, &ProfileList { path: path.display().to_string(), default: file.default, active, profiles })
+}
+
+/// Add a profile, replacing one of the same name
+///
+/// Exit codes: 0 ok, 1 unreadable or unwritable profile file
+pub async fn add(name: String, new: NewProfile, format: OutputFormat) -> Result<()> {
+    let path = config_path()?;
+    let mut file = load(&path)?;
+    let api_key = (new.api_key_env.is_some() || new.api_key_keyring.is_some()).then(|| ApiKeyRef {
+        env: new.api_key_env,
+        keyring: new.api_key_keyring,
+        value: None,
+    });
+    let profile = Profile {
+        server: new.server,
+        api_key,
+        output: new.output.and_then(|format| format.to_possible_value()).map(|value| value.get_name().to_string()),
+        org: new.org,
+        production: new.production,
+    };
+    let action = match file.profiles.insert(name.clone(), profile) {
+        Some(_) => "updated",
+        None => "added",
+    };
+    save(&file, &path)?;
+    output::emit(format, &ProfileChange { profile: name, action: action.to_string(), path: path.display().to_string() })
+}
+
+/// Remove a profile, and its use as the default
+///
+/// Exit codes: 0 ok, 1 unreadable or unwritable profile file, 2 unknown profile
+pub async fn remove(name: String, format: OutputFormat) -> Result<()> {
+    let path = config_path()?;
+    let mut file = load(&path)?;
+    if file.profiles.remove(&name).is_none() {
+        return Err(unknown(&name).into());
+    }
+    if file.default.as_ref() == Some(&name) {
+        file.default = None;
+    }
+    save(&file, &path)?;
+    output::emit(format, &ProfileChange { profile: name, action: "removed".to_string(), path: path.display().to_string() })
+}
+
+/// Use a profile when neither `--profile` nor HAL9_PROFILE selects one
+///
+/// Exit codes: 0 ok, 1 unreadable or unwritable profile file, 2 unknown profile
+pub async fn use_profile(name: String, format: OutputFormat) -> Result<()> {
+    let path = config_path()?;
+    let mut file = load(&path)?;
+    if !file.profiles.contains_key(&name) {
+        return Err(unknown(&name).into());
+    }
+    file.default = Some(name.clone());
+    save(&file, &path)?;
+    output::emit(format, &ProfileChange { profile: name, action: "default".to_string(), path: path.display().to_string() })
+}
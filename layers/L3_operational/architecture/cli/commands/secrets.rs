@@ -0,0 +1,107 @@
+//! Secrets command implementation
+
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+
+use crate::output::{self, GeneratedKey, OutputFormat, SecretsResult};
+
+use hal9_core::secrets::{
+    EnvKeyProvider, FileKeyProvider, MasterKey, SecretsCipher, ENCRYPTED_PREFIX, SECRET_FIELDS,
+};
+
+/// Encrypt plaintext secret fields of a config file in place
+///
+/// Exit codes: 0 ok, 1 missing key or unreadable config
+pub async fn encrypt(config_path: PathBuf, format: OutputFormat) -> Result<()> {
+    let cipher = SecretsCipher::from_provider(&EnvKeyProvider)
+        .context("Failed to load master key")?;
+
+    let count = rewrite_config(&config_path, |value| {
+        if value.starts_with(ENCRYPTED_PREFIX) {
+            Ok(None)
+        } else {
+            Ok(Some(cipher.encrypt(value)?))
+        }
+    })?;
+
+    output::emit(format, &SecretsResult {
+        action: "encrypted".to_string(),
+        config: config_path.display().to_string(),
+        count,
+    })
+}
+
+/// Re-encrypt every secret field of a config file under a new master key
+///
+/// Exit codes: 0 ok, 1 missing key, wrong old key or unreadable config
+pub async fn rotate(config_path: PathBuf, old_key_file: PathBuf, new_key_file: PathBuf, format: OutputFormat) -> Result<()> {
+    let old = SecretsCipher::from_provider(&FileKeyProvider::new(old_key_file))
+        .context("Failed to load old master key")?;
+    let new = SecretsCipher::from_provider(&FileKeyProvider::new(new_key_file))
+        .context("Failed to load new master key")?;
+
+    let count = rewrite_config(&config_path, |value| Ok(Some(old.reencrypt(value, &new)?)))?;
+
+    output::emit(format, &SecretsResult {
+        action: "rotated".to_string(),
+        config: config_path.display().to_string(),
+        count,
+    })
+}
+
+/// Print a fresh master key
+///
+/// Exit codes: 0 ok
+pub async fn generate_key(format: OutputFormat) -> Result<()> {
+    output::emit(format, &GeneratedKey { key: MasterKey::generate().to_base64() })
+}
+
+/// Apply `rewrite` to every secret field present in the config file, then
+/// write it back. Nothing is written if any field fails.
+fn rewrite_config(
+    path: &Path,
+    mut rewrite: impl FnMut(&str) -> hal9_core::Result<Option<String>>,
+) -> Result<usize> {
+    let contents = std::fs::read_to_string(path)
+        .context("Failed to read configuration file")?;
+    let is_yaml = path.extension().unwrap_or_default() == "yaml";
+
+    let mut count = 0;
+    let mut apply = |value: &mut String, field: &str| -> Result<()> {
+        if let Some(rewritten) = rewrite(value).with_context(|| format!("Secret field {}", field))? {
+            *value = rewritten;
+            count += 1;
+        }
+        Ok(())
+    };
+
+    let output = if is_yaml {
+        let mut doc: serde_yaml::Value = serde_yaml::from_str(&contents)
+            .context("Failed to parse YAML configuration")?;
+        for field_path in SECRET_FIELDS {
+            let field = field_path.iter().try_fold(&mut doc, |value, key| value.get_mut(*key));
+            if let Some(serde_yaml::Value::String(value)) = field {
+                apply(value, &field_path.join("."))?;
+            }
+        }
+        serde_yaml::to_string(&doc)?
+    } else {
+        let mut doc: serde_json::Value = serde_json::from_str(&contents)
+            .context("Failed to parse JSON configuration")?;
+        for field_path in SECRET_FIELDS {
+            let field = field_path.iter().try_fold(&mut doc, |value, key| value.get_mut(*key));
+            if let Some(serde_json::Value::String(value)) = field {
+                apply(value, &field_path.join("."))?;
+            }
+        }
+        serde_json::to_string_pretty(&doc)?
+    };
+
+    // Write beside the original and rename, so a failure never leaves a
+    // half-written config
+    let tmp = path.with_extension("tmp");
+    std::fs::write(&tmp, output).context("Failed to write configuration file")?;
+    std::fs::rename(&tmp, path).context("Failed to replace configuration file")?;
+
+    Ok(count)
+}
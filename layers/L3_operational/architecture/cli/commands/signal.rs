@@ -1,17 +1,15 @@
 //! Signal command implementation
 
 use anyhow::Result;
-use colored::Colorize;
 use serde_json::json;
 
-pub async fn execute(from: String, to: String, content: String, server: String) -> Result<()> {
-    println!("{} signal:", "Sending".green());
-    println!("  {}: {}", "From".bold(), from.cyan());
-    println!("  {}: {}", "To".bold(), to.cyan());
-    println!("  {}: {}", "Content".bold(), content);
-    
+use crate::output::{self, CliError, OutputFormat, SignalResult};
+use crate::target::Target;
+
+/// Exit codes: 0 ok, 3 server unreachable, 4 signal rejected
+pub async fn execute(from: String, to: String, content: String, target: Target, format: OutputFormat) -> Result<()> {
     // Create HTTP client
-    let client = reqwest::Client::new();
+    let client = target.client()?;
     
     // Build request payload
     let payload = json!({
@@ -21,38 +19,30 @@ pub async fn execute(from: String, to: String, content: String, server: String)
     });
     
     // Send request
-    let url = format!("http://{}/api/v1/signal", server);
-    println!("\n{} to {}", "Sending".yellow(), url.cyan());
+    let url = target.url("/api/v1/signal");
+    tracing::debug!("Sending signal to {}", url);
     
     let response = client
         .post(&url)
         .json(&payload)
         .send()
-        .await?;
+        .await
+        .map_err(|e| CliError::unreachable(&target.server, e))?;
     
     // Check response
-    if response.status().is_success() {
-        let result: serde_json::Value = response.json().await?;
-        
-        if let Some(signal_id) = result.get("data").and_then(|d| d.get("signal_id")) {
-            println!("\n{} Signal sent successfully!", "✓".green());
-            println!("{}: {}", "Signal ID".bold(), signal_id.as_str().unwrap_or("unknown").yellow());
-        } else {
-            println!("\n{} Signal sent!", "✓".green());
-        }
-        
-        // Show full response in debug mode
-        if std::env::var("DEBUG").is_ok() {
-            println!("\n{}", "Response:".bold());
-            println!("{}", serde_json::to_string_pretty(&result)?);
-        }
-    } else {
-        let status = response.status();
-        let error_text = response.text().await?;
-        println!("\n{} Failed to send signal!", "✗".red());
-        println!("{}: {}", "Status".bold(), status.to_string().red());
-        println!("{}: {}", "Error".bold(), error_text);
+    let status = response.status();
+    if !status.is_success() {
+        let error_text = response.text().await.unwrap_or_default();
+        return Err(CliError::server(format!("Failed to send signal ({}): {}", status, error_text)).into());
     }
     
-    Ok(())
-}
\ No newline at end of file
+    let result: serde_json::Value = response.json().await?;
+    tracing::debug!("Response: {}", result);
+    
+    let signal_id = result.get("data")
+        .and_then(|d| d.get("signal_id"))
+        .and_then(|id| id.as_str())
+        .map(str::to_string);
+    
+    output::emit(format, &SignalResult { from, to, content, signal_id })
+}
@@ -6,33 +6,47 @@ use indicatif::{ProgressBar, ProgressStyle};
 use std::path::PathBuf;
 
 use hal9_core::ServerConfig;
+use hal9_core::secrets::EnvKeyProvider;
 use hal9_server::HAL9Server;
 
-pub async fn execute(config_path: PathBuf, daemon: bool) -> Result<()> {
+use crate::output::{self, OutputFormat, ServerLifecycle};
+
+/// Exit codes: 0 ok, 1 invalid config or failed start
+pub async fn execute(config_path: PathBuf, daemon: bool, format: OutputFormat) -> Result<()> {
+    let text = format == OutputFormat::Text;
     if daemon {
-        println!("{}", "Daemon mode not yet implemented".yellow());
+        // Not a result scripts can act on, so it goes to the log
+        tracing::warn!("Daemon mode not yet implemented");
         return Ok(());
     }
     
-    println!("{} {}", "Loading configuration from".green(), config_path.display());
+    if text {
+        println!("{} {}", "Loading configuration from".green(), config_path.display());
+    }
     
     // Load configuration
     let config_str = std::fs::read_to_string(&config_path)
         .context("Failed to read configuration file")?;
         
-    let config: ServerConfig = if config_path.extension().unwrap_or_default() == "yaml" {
+    let mut config: ServerConfig = if config_path.extension().unwrap_or_default() == "yaml" {
         serde_yaml::from_str(&config_str)
             .context("Failed to parse YAML configuration")?
     } else {
         serde_json::from_str(&config_str)
             .context("Failed to parse JSON configuration")?
     };
+    config.decrypt_secrets(&EnvKeyProvider)
+        .context("Failed to decrypt configuration secrets")?;
     
-    println!("{} {}", "Starting server:".green(), config.server_id.cyan());
-    println!("{} {} neurons", "Configured".green(), config.neurons.len());
+    let server_id = config.server_id.clone();
+    let neurons = config.neurons.len();
     
-    // Create progress bar
-    let pb = ProgressBar::new(config.neurons.len() as u64);
+    // Create progress bar, hidden unless a human is reading
+    let pb = if text {
+        ProgressBar::new(neurons as u64)
+    } else {
+        ProgressBar::hidden()
+    };
     pb.set_style(
         ProgressStyle::default_bar()
             .template("{spinner:.green} [{bar:40.cyan/blue}] {pos}/{len} {msg}")
@@ -48,18 +62,15 @@ pub async fn execute(config_path: PathBuf, daemon: bool) -> Result<()> {
     server.start().await.context("Failed to start server")?;
     pb.finish_with_message(format!("{}", "Server started successfully!".green()));
     
-    println!();
-    println!("{}", "Server is running. Press Ctrl+C to stop.".blue());
-    println!();
+    output::emit(format, &ServerLifecycle { server_id: server_id.clone(), state: "running".to_string(), neurons })?;
     
     // Keep running until interrupted
     tokio::select! {
         _ = tokio::signal::ctrl_c() => {
-            println!("\n{}", "Shutting down...".yellow());
+            tracing::info!("Shutting down...");
             server.shutdown().await?;
-            println!("{}", "Server stopped.".green());
         }
     }
     
-    Ok(())
+    output::emit(format, &ServerLifecycle { server_id, state: "stopped".to_string(), neurons })
 }
\ No newline at end of file
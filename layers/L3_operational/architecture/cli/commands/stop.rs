@@ -1,19 +1,19 @@
 //! Stop command implementation
 
 use anyhow::Result;
-use colored::Colorize;
 
-pub async fn execute(server: String, force: bool) -> Result<()> {
-    if force {
-        println!("{}", "Force stopping server...".red());
-    } else {
-        println!("{}", "Gracefully stopping server...".yellow());
-    }
-    
-    println!("Would connect to: {}", server.cyan());
+use crate::output::{self, OutputFormat, StopResult};
+use crate::target::Target;
+
+/// Exit codes: 0 ok, 2 production server not confirmed
+pub async fn execute(target: Target, force: bool, yes_production: bool, format: OutputFormat) -> Result<()> {
+    target.guard("stop the server", yes_production)?;
     
     // For MVP, just show what would happen
-    println!("{}", "Stop command not yet fully implemented".yellow());
-    
-    Ok(())
-}
\ No newline at end of file
+    output::emit(format, &StopResult {
+        server: target.server,
+        force,
+        stopped: false,
+        message: "Stop command not yet fully implemented".to_string(),
+    })
+}
@@ -0,0 +1,58 @@
+//! Submitting chains from named templates
+
+use anyhow::{Context, Result};
+use serde_json::{json, Map, Value};
+use std::path::Path;
+
+use crate::output::{self, ApiResponse, CliError, OutputFormat, TemplateSubmission};
+use crate::target::Target;
+
+/// A `--var` as the server takes it: `name=value` as text, `name=@path` as
+/// the file at `path`
+///
+/// Exit codes: 1 unreadable file, 2 malformed variable
+pub fn parse_var(var: &str) -> Result<(String, Value)> {
+    let (name, value) = var.split_once('=')
+        .filter(|(name, _)| !name.is_empty())
+        .ok_or_else(|| CliError::usage(format!("Variable '{}' is not name=value or name=@file", var)))?;
+    let Some(path) = value.strip_prefix('@') else {
+        return Ok((name.to_string(), json!(value)));
+    };
+    let path = Path::new(path);
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {} for variable {}", path.display(), name))?;
+    let file = path.file_name().map_or_else(|| path.display().to_string(), |file| file.to_string_lossy().into_owned());
+    Ok((name.to_string(), json!({ "file": file, "content": content })))
+}
+
+/// Render `template` with `vars` on the server and start its chain
+///
+/// Exit codes: 0 ok, 1 unreadable file, 2 malformed variable, 3 server
+/// unreachable, 4 submission rejected
+pub async fn execute(
+    target: Target,
+    template: String,
+    vars: Vec<String>,
+    version: Option<u32>,
+    format: OutputFormat,
+) -> Result<()> {
+    let mut variables = Map::new();
+    for var in &vars {
+        let (name, value) = parse_var(var)?;
+        variables.insert(name, value);
+    }
+    let client = target.client()?;
+
+    let url = target.url(&format!("/api/v1/templates/{}/submit", template));
+    let response = client.post(&url).json(&json!({ "variables": variables, "version": version })).send().await
+        .map_err(|e| CliError::unreachable(&target.server, e))?;
+
+    let status = response.status();
+    if !status.is_success() {
+        let error_text = response.text().await.unwrap_or_default();
+        return Err(CliError::server(format!("Failed to submit template {} ({}): {}", template, status, error_text)).into());
+    }
+
+    let submission = response.json::<ApiResponse<TemplateSubmission>>().await?.into_data()?;
+    output::emit(format, &submission)
+}
@@ -1,13 +1,22 @@
 //! 2HAL9 CLI - Command line interface for 2HAL9 neural network
 
-use anyhow::Result;
-use clap::{Parser, Subcommand};
+use clap::builder::PossibleValuesParser;
+use clap::{CommandFactory, Parser, Subcommand};
+use clap_complete::Shell;
 use colored::Colorize;
 use std::path::PathBuf;
-use tracing::error;
 
 mod commands;
-use commands::{start, status, signal, stop};
+mod output;
+mod target;
+use clap::ValueEnum;
+use commands::{start, dev, status, signal, submit, stop, secrets, logs, list, memory, chain, admin, db, profile, completions};
+use hal9_client::profile::{ProfileError, ProfileFile, ResolvedProfile};
+use output::{CliError, ExitCode, OutputFormat};
+use target::Target;
+
+const EXIT_CODES: &str = "Exit codes: 0 success, 1 local failure, 2 invalid arguments, \
+3 server unreachable, 4 server error";
 
 #[derive(Parser)]
 #[command(
@@ -15,7 +24,8 @@ use commands::{start, status, signal, stop};
     about = "2HAL9 - Hierarchical AI Neural Network",
     version,
     author,
-    long_about = "A distributed AI consciousness system implementing hierarchical abstraction through networked AI neurons"
+    long_about = "A distributed AI consciousness system implementing hierarchical abstraction through networked AI neurons",
+    after_help = EXIT_CODES
 )]
 struct Cli {
     #[command(subcommand)]
@@ -28,11 +38,24 @@ struct Cli {
     /// Quiet mode (errors only)
     #[arg(short, long, global = true)]
     quiet: bool,
+    
+    /// Output format; json and yaml have stable schemas for scripting [default: the profile's, else text]
+    #[arg(short, long, global = true, value_enum)]
+    output: Option<OutputFormat>,
+    
+    /// Profile from ~/.config/hal9/config.toml [default: $HAL9_PROFILE, else the file's default]
+    #[arg(long, global = true)]
+    profile: Option<String>,
+    
+    /// Run destructive commands against a production profile's server without asking
+    #[arg(long, global = true)]
+    yes_production: bool,
 }
 
 #[derive(Subcommand)]
 enum Commands {
     /// Start a 2HAL9 server
+    #[command(after_help = "Exit codes: 0 stopped cleanly, 1 invalid config or failed start")]
     Start {
         /// Configuration file path
         #[arg(short, long, default_value = "config.yaml")]
@@ -43,18 +66,40 @@ enum Commands {
         daemon: bool,
     },
     
+    /// Run a local server for development: mock Claude, in-memory databases and demo users
+    #[command(after_help = "Exit codes: 0 stopped cleanly, 1 failed start or scenario, 2 unknown scenario")]
+    Dev {
+        /// MVP demo scenario to replay and run at startup
+        #[arg(long, value_parser = PossibleValuesParser::new(hal9_server::dev::SCENARIOS.iter().map(|(name, _, _)| *name)))]
+        scenario: Option<String>,
+        
+        /// Topology file to start from, reloaded whenever it changes [default: the L4/L3/L2 trio while it does not exist]
+        #[arg(short, long, default_value = hal9_server::dev::TOPOLOGY_FILE)]
+        topology: PathBuf,
+        
+        /// HTTP port, on localhost
+        #[arg(long, default_value_t = 8080)]
+        port: u16,
+    },
+    
     /// Show server status
+    #[command(after_help = "Exit codes: 0 ok, 3 server unreachable, 4 server error")]
     Status {
-        /// Server address
-        #[arg(short, long, default_value = "localhost:8080")]
-        server: String,
-        
-        /// Output format (text, json)
-        #[arg(short, long, default_value = "text")]
-        format: String,
+        /// Server address [default: the profile's, else localhost:8080]
+        #[arg(short, long)]
+        server: Option<String>,
+    },
+    
+    /// Show API cost and budget usage
+    #[command(after_help = "Exit codes: 0 ok, 3 server unreachable, 4 server error")]
+    Costs {
+        /// Server address [default: the profile's, else localhost:8080]
+        #[arg(short, long)]
+        server: Option<String>,
     },
     
     /// Send a signal to a neuron
+    #[command(after_help = "Exit codes: 0 ok, 3 server unreachable, 4 signal rejected")]
     Signal {
         /// Source neuron (use "user" for external input)
         #[arg(short, long, default_value = "user")]
@@ -68,27 +113,386 @@ enum Commands {
         #[arg(short, long)]
         content: String,
         
-        /// Server address
-        #[arg(short, long, default_value = "localhost:8080")]
-        server: String,
+        /// Server address [default: the profile's, else localhost:8080]
+        #[arg(short, long)]
+        server: Option<String>,
+    },
+    
+    /// Start a chain from a named template, e.g. --template triage --var report=@bug.txt
+    #[command(after_help = "Exit codes: 0 ok, 1 unreadable file, 2 malformed variable, 3 server unreachable, \
+4 submission rejected")]
+    Submit {
+        /// Template name
+        #[arg(short, long)]
+        template: String,
+        
+        /// Variable as name=value, or name=@path to send a file (repeatable)
+        #[arg(long = "var")]
+        vars: Vec<String>,
+        
+        /// Template version to render [default: the latest]
+        #[arg(long)]
+        template_version: Option<u32>,
+        
+        /// Server address [default: the profile's, else localhost:8080]
+        #[arg(short, long)]
+        server: Option<String>,
     },
     
     /// Stop a running server
+    #[command(after_help = "Exit codes: 0 ok, 2 production server not confirmed")]
     Stop {
-        /// Server address
-        #[arg(short, long, default_value = "localhost:8080")]
-        server: String,
+        /// Server address [default: the profile's, else localhost:8080]
+        #[arg(short, long)]
+        server: Option<String>,
         
         /// Force shutdown without grace period
         #[arg(short, long)]
         force: bool,
     },
+    
+    /// Show recent server logs, optionally for one chain
+    #[command(after_help = "Exit codes: 0 ok, 3 server unreachable, 4 server error")]
+    Logs {
+        /// Only entries for this chain ID
+        #[arg(short, long)]
+        chain: Option<String>,
+        
+        /// Minimum level (error, warn, info, debug, trace)
+        #[arg(short, long)]
+        level: Option<String>,
+        
+        /// Time window (e.g. 30m, 2h) or RFC 3339 timestamp
+        #[arg(long, default_value = "1h")]
+        since: String,
+        
+        /// Maximum entries to show
+        #[arg(short = 'n', long, default_value_t = 200)]
+        limit: usize,
+        
+        /// Server address [default: the profile's, else localhost:8080]
+        #[arg(short, long)]
+        server: Option<String>,
+    },
+    
+    /// List neurons, scaling-events, webhooks, deliveries or errors
+    #[command(after_help = "Exit codes: 0 ok, 2 invalid resource or filter, 3 server unreachable, 4 server error")]
+    List {
+        /// Resource to list
+        resource: String,
+        
+        /// Webhook whose deliveries to list
+        #[arg(long)]
+        webhook: Option<String>,
+        
+        /// Field filter as field=value (repeatable; comma-separate alternatives)
+        #[arg(long = "filter")]
+        filters: Vec<String>,
+        
+        /// Sort field, prefixed with - for descending
+        #[arg(long)]
+        sort: Option<String>,
+        
+        /// Page size
+        #[arg(short = 'n', long)]
+        limit: Option<usize>,
+        
+        /// Follow cursors and fetch every page
+        #[arg(long)]
+        all: bool,
+        
+        /// Server address [default: the profile's, else localhost:8080]
+        #[arg(short, long)]
+        server: Option<String>,
+    },
+    
+    /// Start, replay or compare chains, e.g. a chain and its replay
+    Chain {
+        #[command(subcommand)]
+        action: ChainAction,
+    },
+    
+    /// Export or import neuron memories, e.g. to promote them from staging
+    Memory {
+        #[command(subcommand)]
+        action: MemoryAction,
+    },
+    
+    /// Server maintenance, e.g. compacting its databases
+    Admin {
+        #[command(subcommand)]
+        action: AdminAction,
+    },
+    
+    /// Manage encrypted configuration secrets
+    #[command(after_help = "Exit codes: 0 ok, 1 missing or wrong key, unreadable config")]
+    Secrets {
+        #[command(subcommand)]
+        action: SecretsAction,
+    },
+    
+    /// Inspect the server database
+    Db {
+        #[command(subcommand)]
+        action: DbAction,
+    },
+    
+    /// Manage named server profiles, e.g. staging and production
+    Profile {
+        #[command(subcommand)]
+        action: ProfileAction,
+    },
+    
+    /// Print a shell completion script
+    #[command(after_help = "Exit codes: 0 ok, 2 unknown shell")]
+    Completions {
+        /// Shell to generate completions for
+        shell: Shell,
+    },
+}
+
+#[derive(Subcommand)]
+enum ChainAction {
+    /// Start a chain, e.g. the next turn of a session: --session new, then --session <id>
+    #[command(after_help = "Exit codes: 0 ok, 3 server unreachable, 4 session unknown or submission rejected")]
+    Start {
+        /// Input of the chain
+        content: String,
+        
+        /// Entry neuron [default: the layer's]
+        #[arg(short, long)]
+        neuron: Option<String>,
+        
+        /// Layer of the entry neuron
+        #[arg(short, long, default_value = "L4")]
+        layer: String,
+        
+        /// Session to continue, or "new" to start one; the chain sees the conversation so far
+        #[arg(long)]
+        session: Option<String>,
+        
+        /// Server address [default: the profile's, else localhost:8080]
+        #[arg(short, long)]
+        server: Option<String>,
+    },
+    
+    /// Start a chain again from its root signal, linked to the original
+    #[command(after_help = "Exit codes: 0 ok, 3 server unreachable, 4 chain unknown or replay rejected")]
+    Replay {
+        /// Chain ID to replay
+        chain: String,
+        
+        /// Input to use instead of the original, e.g. a tweaked prompt
+        #[arg(short, long)]
+        content: Option<String>,
+        
+        /// Server address [default: the profile's, else localhost:8080]
+        #[arg(short, long)]
+        server: Option<String>,
+    },
+    
+    /// Show node by node how chain B differs from chain A
+    #[command(after_help = "Exit codes: 0 ok, 3 server unreachable, 4 chain unknown")]
+    Compare {
+        /// Chain to compare against, e.g. the original
+        a: String,
+        
+        /// Chain to compare, e.g. its replay
+        b: String,
+        
+        /// Server address [default: the profile's, else localhost:8080]
+        #[arg(short, long)]
+        server: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum MemoryAction {
+    /// Download a memory dump (JSON lines)
+    #[command(after_help = "Exit codes: 0 ok, 1 unwritable file, 3 server unreachable, 4 server error")]
+    Export {
+        /// Only memories of this namespace, e.g. private:neuron-l2
+        #[arg(long)]
+        namespace: Option<String>,
+        
+        /// Only memories of this neuron
+        #[arg(long)]
+        neuron: Option<String>,
+        
+        /// Write the dump here instead of to stdout
+        #[arg(short, long)]
+        file: Option<PathBuf>,
+        
+        /// Server address [default: the profile's, else localhost:8080]
+        #[arg(short, long)]
+        server: Option<String>,
+    },
+    
+    /// Load a memory dump
+    #[command(after_help = "Exit codes: 0 ok, 1 unreadable file, 3 server unreachable, 4 dump rejected")]
+    Import {
+        /// Dump written by `hal9 memory export`
+        file: PathBuf,
+        
+        /// What to do with keys that already exist: skip, overwrite or merge-newer
+        #[arg(long, default_value = "skip", value_parser = ["skip", "overwrite", "merge-newer"])]
+        policy: String,
+        
+        /// Report what would change without writing anything
+        #[arg(long)]
+        dry_run: bool,
+        
+        /// Regenerate embeddings made by a different embedding provider
+        #[arg(long)]
+        recompute_embeddings: bool,
+        
+        /// Server address [default: the profile's, else localhost:8080]
+        #[arg(short, long)]
+        server: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum AdminAction {
+    /// VACUUM and ANALYZE the server's SQLite databases, reporting the space freed
+    #[command(after_help = "Exit codes: 0 ok, 3 server unreachable, 4 compaction failed")]
+    Compact {
+        /// Server address [default: the profile's, else localhost:8080]
+        #[arg(short, long)]
+        server: Option<String>,
+    },
+    
+    /// Hot and cold memory tiers
+    Tiering {
+        #[command(subcommand)]
+        action: TieringAction,
+    },
+}
+
+#[derive(Subcommand)]
+enum TieringAction {
+    /// Show the entries and bytes in each memory tier and the last tiering run
+    #[command(after_help = "Exit codes: 0 ok, 3 server unreachable, 4 memory disabled or unreadable")]
+    Status {
+        /// Server address [default: the profile's, else localhost:8080]
+        #[arg(short, long)]
+        server: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum DbAction {
+    /// Show the database's schema version against the one this release expects
+    #[command(after_help = "Exit codes: 0 current or behind, 1 unreadable config or database, \
+4 migrated by a newer release or a migration failed partway")]
+    Status {
+        /// Configuration file path
+        #[arg(short, long, default_value = "config.yaml")]
+        config: PathBuf,
+    },
+}
+
+#[derive(Subcommand)]
+enum ProfileAction {
+    /// List profiles, marking the selected one
+    #[command(after_help = "Exit codes: 0 ok, 1 unreadable profile file")]
+    List,
+    
+    /// Add a profile, or replace one of the same name
+    #[command(after_help = "Exit codes: 0 ok, 1 unreadable or unwritable profile file")]
+    Add {
+        /// Profile name
+        name: String,
+        
+        /// Server URL, or host:port for plain HTTP
+        #[arg(long)]
+        server: String,
+        
+        /// Environment variable holding the API key
+        #[arg(long)]
+        api_key_env: Option<String>,
+        
+        /// System keyring entry holding the API key, tried before --api-key-env
+        #[arg(long)]
+        api_key_keyring: Option<String>,
+        
+        /// Default output format
+        #[arg(long, value_enum)]
+        default_output: Option<OutputFormat>,
+        
+        /// Default organization
+        #[arg(long)]
+        org: Option<String>,
+        
+        /// Require confirming destructive commands against this server
+        #[arg(long)]
+        production: bool,
+    },
+    
+    /// Remove a profile
+    #[command(after_help = "Exit codes: 0 ok, 1 unreadable or unwritable profile file, 2 unknown profile")]
+    Remove {
+        /// Profile name
+        name: String,
+    },
+    
+    /// Use a profile when neither --profile nor HAL9_PROFILE selects one
+    #[command(after_help = "Exit codes: 0 ok, 1 unreadable or unwritable profile file, 2 unknown profile")]
+    Use {
+        /// Profile name
+        name: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum SecretsAction {
+    /// Encrypt plaintext secret fields in place (key from HAL9_MASTER_KEY or HAL9_MASTER_KEY_FILE)
+    Encrypt {
+        /// Configuration file path
+        #[arg(short, long, default_value = "config.yaml")]
+        config: PathBuf,
+    },
+    
+    /// Re-encrypt secret fields under a new master key
+    Rotate {
+        /// Configuration file path
+        #[arg(short, long, default_value = "config.yaml")]
+        config: PathBuf,
+        
+        /// File holding the current master key
+        #[arg(long)]
+        old_key_file: PathBuf,
+        
+        /// File holding the new master key
+        #[arg(long)]
+        new_key_file: PathBuf,
+    },
+    
+    /// Print a new random master key
+    GenerateKey,
 }
 
 #[tokio::main]
-async fn main() -> Result<()> {
+async fn main() {
     let cli = Cli::parse();
     
+    // Managing profiles and printing completions work with any profile selected
+    let (profiles, selected) = match cli.command {
+        Commands::Profile { .. } | Commands::Completions { .. } => (ProfileFile::default(), None),
+        _ => match load_profile(cli.profile.as_deref()) {
+            Ok(loaded) => loaded,
+            Err(e) => {
+                let format = cli.output.unwrap_or(OutputFormat::Text);
+                output::emit_error(format, &e);
+                std::process::exit(output::exit_code(&e));
+            }
+        },
+    };
+    let format = cli.output
+        .or_else(|| selected.as_ref()?.output.as_deref().and_then(|o| OutputFormat::from_str(o, true).ok()))
+        .unwrap_or(OutputFormat::Text);
+    let target = |server: Option<String>| Target::new(server, selected.as_ref(), &profiles);
+    
     // Initialize logging
     let log_level = if cli.quiet {
         "error"
@@ -98,40 +502,131 @@ async fn main() -> Result<()> {
         "info"
     };
     
+    // Logs go to stderr so stdout holds only command output
     tracing_subscriber::fmt()
         .with_env_filter(log_level)
         .with_target(false)
         .with_thread_ids(false)
         .with_thread_names(false)
+        .with_writer(std::io::stderr)
         .init();
     
-    // Print banner
-    if !cli.quiet {
+    // Print banner, except ahead of output meant for a file or shell
+    let scripted = matches!(
+        cli.command,
+        Commands::Completions { .. } | Commands::Memory { action: MemoryAction::Export { file: None, .. } }
+    );
+    if !cli.quiet && !scripted && format == OutputFormat::Text {
         print_banner();
     }
     
     // Execute command
     let result = match cli.command {
         Commands::Start { config, daemon } => {
-            start::execute(config, daemon).await
+            start::execute(config, daemon, format).await
         }
-        Commands::Status { server, format } => {
-            status::execute(server, format).await
+        Commands::Dev { scenario, topology, port } => {
+            dev::execute(scenario, topology, port, format).await
+        }
+        Commands::Status { server } => {
+            status::execute(target(server), format).await
+        }
+        Commands::Costs { server } => {
+            status::costs(target(server), format).await
         }
         Commands::Signal { from, to, content, server } => {
-            signal::execute(from, to, content, server).await
+            signal::execute(from, to, content, target(server), format).await
+        }
+        Commands::Submit { template, vars, template_version, server } => {
+            submit::execute(target(server), template, vars, template_version, format).await
         }
         Commands::Stop { server, force } => {
-            stop::execute(server, force).await
+            stop::execute(target(server), force, cli.yes_production, format).await
+        }
+        Commands::Logs { chain, level, since, limit, server } => {
+            logs::execute(target(server), chain, level, since, limit, format).await
+        }
+        Commands::List { resource, webhook, filters, sort, limit, all, server } => {
+            list::execute(resource, webhook, filters, sort, limit, all, target(server), format).await
+        }
+        Commands::Chain { action } => match action {
+            ChainAction::Start { content, neuron, layer, session, server } => {
+                chain::start(target(server), content, neuron, layer, session, format).await
+            }
+            ChainAction::Replay { chain: chain_id, content, server } => {
+                chain::replay(target(server), chain_id, content, format).await
+            }
+            ChainAction::Compare { a, b, server } => {
+                chain::compare(target(server), a, b, format).await
+            }
+        },
+        Commands::Memory { action } => match action {
+            MemoryAction::Export { namespace, neuron, file, server } => {
+                memory::export(target(server), namespace, neuron, file, format).await
+            }
+            MemoryAction::Import { file, policy, dry_run, recompute_embeddings, server } => {
+                memory::import(target(server), file, policy, dry_run, recompute_embeddings, format).await
+            }
+        },
+        Commands::Admin { action } => match action {
+            AdminAction::Compact { server } => {
+                admin::compact(target(server), format).await
+            }
+            AdminAction::Tiering { action: TieringAction::Status { server } } => {
+                admin::tiering_status(target(server), format).await
+            }
+        },
+        Commands::Secrets { action } => match action {
+            SecretsAction::Encrypt { config } => {
+                secrets::encrypt(config, format).await
+            }
+            SecretsAction::Rotate { config, old_key_file, new_key_file } => {
+                secrets::rotate(config, old_key_file, new_key_file, format).await
+            }
+            SecretsAction::GenerateKey => {
+                secrets::generate_key(format).await
+            }
+        },
+        Commands::Db { action } => match action {
+            DbAction::Status { config } => {
+                db::status(config, format).await
+            }
+        },
+        Commands::Profile { action } => match action {
+            ProfileAction::List => {
+                profile::list(cli.profile, format).await
+            }
+            ProfileAction::Add { name, server, api_key_env, api_key_keyring, default_output, org, production } => {
+                let new = profile::NewProfile { server, api_key_env, api_key_keyring, output: default_output, org, production };
+                profile::add(name, new, format).await
+            }
+            ProfileAction::Remove { name } => {
+                profile::remove(name, format).await
+            }
+            ProfileAction::Use { name } => {
+                profile::use_profile(name, format).await
+            }
+        },
+        Commands::Completions { shell } => {
+            completions::execute(shell, Cli::command()).await
         }
     };
     
     if let Err(e) = result {
-        error!("{}", e.to_string().red());
-        std::process::exit(1);
+        output::emit_error(format, &e);
+        std::process::exit(output::exit_code(&e));
     }
-    
-    Ok(())
+}
+
+/// The profile file and the profile selected by `--profile`, HAL9_PROFILE or
+/// the file's default, if any
+fn load_profile(flag: Option<&str>) -> anyhow::Result<(ProfileFile, Option<ResolvedProfile>)> {
+    let profiles = ProfileFile::load_default().map_err(|e| CliError::new(ExitCode::Failure, e.to_string()))?;
+    let selected = profiles.resolve_from_env(flag).map_err(|e| match e {
+        ProfileError::Unknown(_) => CliError::usage(format!("{}; see `hal9 profile list`", e)),
+        e => CliError::new(ExitCode::Failure, e.to_string()),
+    })?;
+    Ok((profiles, selected))
 }
 
 fn print_banner() {
@@ -144,4 +639,85 @@ fn print_banner() {
     "#.cyan());
     println!("{}", "Hierarchical AI Neural Network".blue());
     println!();
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cli_definition_is_valid() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn test_completions_for_every_shell() {
+        for shell in Shell::value_variants() {
+            let mut script = Vec::new();
+            completions::generate(*shell, &mut Cli::command(), &mut script);
+            let script = String::from_utf8(script).unwrap();
+            assert!(script.contains("hal9"), "{} completions", shell);
+            assert!(script.contains("completions"), "{} completions lack subcommands", shell);
+        }
+    }
+
+    #[test]
+    fn test_output_flag_is_global() {
+        let cli = Cli::try_parse_from(["hal9", "status", "--output", "json"]).unwrap();
+        assert_eq!(cli.output, Some(OutputFormat::Json));
+        let cli = Cli::try_parse_from(["hal9", "-o", "yaml", "secrets", "generate-key"]).unwrap();
+        assert_eq!(cli.output, Some(OutputFormat::Yaml));
+        assert!(Cli::try_parse_from(["hal9", "status", "--output", "xml"]).is_err());
+    }
+
+    #[test]
+    fn test_profile_flags_are_global() {
+        let cli = Cli::try_parse_from(["hal9", "stop", "--profile", "production", "--yes-production"]).unwrap();
+        assert_eq!(cli.profile.as_deref(), Some("production"));
+        assert!(cli.yes_production);
+        let Commands::Stop { server, .. } = cli.command else { panic!("not stop") };
+        assert_eq!(server, None);
+        
+        let cli = Cli::try_parse_from(["hal9", "--profile", "staging", "status", "-s", "localhost:9090"]).unwrap();
+        assert_eq!(cli.profile.as_deref(), Some("staging"));
+        assert!(!cli.yes_production);
+        assert_eq!(cli.output, None);
+    }
+
+    #[test]
+    fn test_submit_takes_repeated_vars() {
+        let cli = Cli::try_parse_from([
+            "hal9", "submit", "--template", "triage", "--var", "report=@bug.txt", "--var", "severity=high",
+        ]).unwrap();
+        let Commands::Submit { template, vars, template_version, .. } = cli.command else { panic!("not submit") };
+        assert_eq!(template, "triage");
+        assert_eq!(vars, vec!["report=@bug.txt", "severity=high"]);
+        assert_eq!(template_version, None);
+        assert!(Cli::try_parse_from(["hal9", "submit", "--var", "severity=high"]).is_err());
+
+        assert_eq!(submit::parse_var("title=a=b").unwrap(), ("title".to_string(), serde_json::json!("a=b")));
+        assert_eq!(output::exit_code(&submit::parse_var("severity").unwrap_err()), ExitCode::Usage as i32);
+        assert_eq!(output::exit_code(&submit::parse_var("report=@/nonexistent/bug.txt").unwrap_err()), ExitCode::Failure as i32);
+    }
+
+    #[test]
+    fn test_dev_takes_a_known_scenario() {
+        let cli = Cli::try_parse_from(["hal9", "dev", "--scenario", "task-manager"]).unwrap();
+        let Commands::Dev { scenario, topology, port } = cli.command else { panic!("not dev") };
+        assert_eq!(scenario.as_deref(), Some("task-manager"));
+        assert_eq!((topology, port), (PathBuf::from("topology.yaml"), 8080));
+        
+        let error = Cli::try_parse_from(["hal9", "dev", "--scenario", "unknown"]).err().unwrap();
+        assert_eq!(error.exit_code(), ExitCode::Usage as i32);
+    }
+
+    #[test]
+    fn test_chain_start_takes_a_session() {
+        let cli = Cli::try_parse_from(["hal9", "chain", "start", "And in French?", "--session", "new"]).unwrap();
+        let Commands::Chain { action: ChainAction::Start { content, neuron, layer, session, .. } } = cli.command else {
+            panic!("not chain start")
+        };
+        assert_eq!((content.as_str(), layer.as_str()), ("And in French?", "L4"));
+        assert_eq!((neuron, session.as_deref()), (None, Some("new")));
+    }
+}
@@ -0,0 +1,1456 @@
+//! Command output in text, JSON or YAML
+//!
+//! Every command builds one of the report structs below and hands it to
+//! [`emit`]. JSON and YAML are the serialized struct; text is rendered from
+//! the same struct, so the formats can't drift apart. The JSON field names
+//! are a stable interface for scripts: add fields, don't rename or remove
+//! them.
+//!
+//! Exit codes, shared by all commands:
+//!
+//! | code | meaning                                          |
+//! |------|--------------------------------------------------|
+//! | 0    | success                                          |
+//! | 1    | local failure (config file, keys, server start)  |
+//! | 2    | invalid arguments                                |
+//! | 3    | server unreachable                               |
+//! | 4    | server answered with an error or no data         |
+
+use anyhow::Result;
+use clap::ValueEnum;
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt::{self, Write};
+
+/// Output format selected with `--output`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    Text,
+    Json,
+    Yaml,
+}
+
+/// Process exit codes, see the module docs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitCode {
+    Failure = 1,
+    Usage = 2,
+    Unreachable = 3,
+    ServerError = 4,
+}
+
+/// An error carrying the exit code it should produce
+#[derive(Debug)]
+pub struct CliError {
+    pub code: ExitCode,
+    pub message: String,
+}
+
+impl CliError {
+    pub fn new(code: ExitCode, message: impl Into<String>) -> Self {
+        Self { code, message: message.into() }
+    }
+
+    pub fn usage(message: impl Into<String>) -> Self {
+        Self::new(ExitCode::Usage, message)
+    }
+
+    /// The server at `server` could not be reached
+    pub fn unreachable(server: &str, error: impl fmt::Display) -> Self {
+        Self::new(
+            ExitCode::Unreachable,
+            format!("Failed to connect to server at {}: {}", server, error),
+        )
+    }
+
+    pub fn server(message: impl Into<String>) -> Self {
+        Self::new(ExitCode::ServerError, message)
+    }
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for CliError {}
+
+/// Exit code for any error a command returned
+pub fn exit_code(error: &anyhow::Error) -> i32 {
+    error.downcast_ref::<CliError>()
+        .map(|e| e.code)
+        .unwrap_or(ExitCode::Failure) as i32
+}
+
+/// Error body printed in JSON and YAML modes
+#[derive(Debug, Serialize)]
+pub struct ErrorReport {
+    pub error: ErrorDetail,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ErrorDetail {
+    pub code: i32,
+    pub message: String,
+}
+
+/// Print a failed command's error in the selected format
+pub fn emit_error(format: OutputFormat, error: &anyhow::Error) {
+    let report = ErrorReport {
+        error: ErrorDetail { code: exit_code(error), message: format!("{:#}", error) },
+    };
+    match format {
+        OutputFormat::Text => eprintln!("{} {}", "✗".red(), report.error.message.red()),
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&report).unwrap_or_default()),
+        OutputFormat::Yaml => print!("{}", serde_yaml::to_string(&report).unwrap_or_default()),
+    }
+}
+
+/// A command result that can also be shown to humans
+pub trait Render: Serialize {
+    /// Human-readable rendering, with a trailing newline
+    fn render_text(&self, out: &mut String) -> fmt::Result;
+}
+
+/// Serialize or render `report` in `format`
+pub fn format<T: Render>(format: OutputFormat, report: &T) -> Result<String> {
+    Ok(match format {
+        OutputFormat::Text => {
+            let mut out = String::new();
+            report.render_text(&mut out)?;
+            out
+        }
+        OutputFormat::Json => serde_json::to_string_pretty(report)? + "\n",
+        OutputFormat::Yaml => serde_yaml::to_string(report)?,
+    })
+}
+
+/// Print `report` to stdout in `format`
+pub fn emit<T: Render>(output: OutputFormat, report: &T) -> Result<()> {
+    print!("{}", format(output, report)?);
+    Ok(())
+}
+
+/// Envelope every server API response uses
+#[derive(Debug, Deserialize)]
+pub struct ApiResponse<T> {
+    pub data: Option<T>,
+    pub error: Option<String>,
+}
+
+impl<T> ApiResponse<T> {
+    /// The payload, or the server's error
+    pub fn into_data(self) -> Result<T, CliError> {
+        self.data.ok_or_else(|| {
+            CliError::server(self.error.unwrap_or_else(|| "Server returned no data".to_string()))
+        })
+    }
+}
+
+// Status
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StatusReport {
+    pub server: String,
+    pub running: bool,
+    pub uptime_seconds: u64,
+    pub neurons: Vec<NeuronStatus>,
+    pub metrics: MetricsSummary,
+    /// Empty for servers without layer pauses
+    #[serde(default)]
+    pub paused_layers: Vec<PausedLayer>,
+    /// Absent for servers without read-only mode
+    #[serde(default)]
+    pub read_only: Option<ReadOnlyMode>,
+    /// Empty for servers without SLOs
+    #[serde(default)]
+    pub slos: Vec<SloState>,
+    /// Absent for servers without cost tracking
+    pub costs: Option<CostReport>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PausedLayer {
+    pub layer: String,
+    pub reason: Option<String>,
+    pub paused_by: Option<String>,
+    pub paused_at: String,
+    pub resume_at: Option<String>,
+    pub held: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReadOnlyMode {
+    pub active: bool,
+    #[serde(default)]
+    pub reason: Option<String>,
+    #[serde(default)]
+    pub enabled_by: Option<String>,
+    #[serde(default)]
+    pub since: Option<String>,
+    #[serde(default)]
+    pub retry_after_secs: Option<u64>,
+    pub memory_writes: String,
+    pub held_writes: usize,
+    pub skipped_writes: u64,
+    pub refused_requests: u64,
+    /// Databases whose reads go to their replica
+    #[serde(default)]
+    pub replica_reads: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SloState {
+    pub name: String,
+    pub layer: Option<String>,
+    pub latency_ms: u64,
+    pub objective: f64,
+    pub compliance: f64,
+    pub compliant: bool,
+    pub budget_remaining: f64,
+    pub fast_burn_rate: f64,
+    pub slow_burn_rate: f64,
+    pub projected_exhaustion: Option<String>,
+    pub alert: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NeuronStatus {
+    pub id: String,
+    pub layer: String,
+    pub state: String,
+    pub health: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MetricsSummary {
+    pub signals_sent: u64,
+    pub signals_processed: u64,
+    pub signals_failed: u64,
+    pub average_latency_ms: f64,
+}
+
+impl Render for StatusReport {
+    fn render_text(&self, out: &mut String) -> fmt::Result {
+        writeln!(out, "\n{}", "HAL9 Server Status".bold().underline())?;
+        writeln!(out, "{}: {}", "Status".bold(), if self.running { "Running".green() } else { "Stopped".red() })?;
+        writeln!(out, "{}: {}", "Uptime".bold(), format_duration(self.uptime_seconds))?;
+
+        if let Some(read_only) = self.read_only.as_ref().filter(|read_only| read_only.active) {
+            let banner = format!(
+                "READ-ONLY since {} ({})",
+                read_only.since.as_deref().unwrap_or("unknown"),
+                read_only.reason.as_deref().unwrap_or("no reason given")
+            );
+            writeln!(out, "{}: {}", "Mode".bold(), banner.red().bold())?;
+            writeln!(out, "{}: {} ({} held, {} skipped)",
+                "Memory writes".bold(),
+                read_only.memory_writes,
+                read_only.held_writes,
+                read_only.skipped_writes
+            )?;
+            writeln!(out, "{}: {}", "Refused requests".bold(), read_only.refused_requests)?;
+            if !read_only.replica_reads.is_empty() {
+                writeln!(out, "{}: {}", "Reads from replica".bold(), read_only.replica_reads.join(", "))?;
+            }
+        }
+
+        if !self.neurons.is_empty() {
+            writeln!(out, "\n{}", "Neurons".bold().underline())?;
+            writeln!(out, "{:<20} {:<10} {:<15} {:<10}", "ID", "Layer", "State", "Health")?;
+            writeln!(out, "{}", "-".repeat(55))?;
+
+            for neuron in &self.neurons {
+                let state_colored = match neuron.state.as_str() {
+                    "Running" => neuron.state.green(),
+                    "Stopped" => neuron.state.red(),
+                    _ => neuron.state.yellow(),
+                };
+
+                let health_colored = match neuron.health.as_str() {
+                    "Healthy" => neuron.health.green(),
+                    "Unhealthy" => neuron.health.red(),
+                    _ => neuron.health.yellow(),
+                };
+
+                writeln!(out, "{:<20} {:<10} {:<15} {:<10}",
+                    neuron.id.cyan(),
+                    neuron.layer,
+                    state_colored,
+                    health_colored
+                )?;
+            }
+        }
+
+        if !self.paused_layers.is_empty() {
+            writeln!(out, "\n{}", "Paused Layers".bold().underline())?;
+            writeln!(out, "{:<8} {:<8} {:<26} {}", "Layer", "Held", "Resumes", "Reason")?;
+            writeln!(out, "{}", "-".repeat(55))?;
+
+            for paused in &self.paused_layers {
+                writeln!(out, "{:<8} {:<8} {:<26} {}",
+                    paused.layer.yellow(),
+                    paused.held,
+                    paused.resume_at.as_deref().unwrap_or("manually"),
+                    paused.reason.as_deref().unwrap_or("-")
+                )?;
+            }
+        }
+
+        if !self.slos.is_empty() {
+            writeln!(out, "\n{}", "SLOs".bold().underline())?;
+            writeln!(out, "{:<20} {:<8} {:<18} {:<12} {:<10} {:<12} {}",
+                "Name", "Scope", "Target", "Compliance", "Budget", "Burn 1h/6h", "Alert")?;
+            writeln!(out, "{}", "-".repeat(90))?;
+
+            for slo in &self.slos {
+                let compliance = format!("{:.2}%", slo.compliance * 100.0);
+                let compliance = if slo.compliant { compliance.green() } else { compliance.red() };
+                let budget = format!("{:.0}%", slo.budget_remaining * 100.0);
+                let budget = if slo.budget_remaining <= 0.0 {
+                    budget.red()
+                } else if slo.budget_remaining < 0.2 {
+                    budget.yellow()
+                } else {
+                    budget.green()
+                };
+
+                writeln!(out, "{:<20} {:<8} {:<18} {:<12} {:<10} {:<12} {}",
+                    slo.name.cyan(),
+                    slo.layer.as_deref().unwrap_or("chains"),
+                    format!("{:.2}% < {}ms", slo.objective * 100.0, slo.latency_ms),
+                    compliance,
+                    budget,
+                    format!("{:.1}/{:.1}", slo.fast_burn_rate, slo.slow_burn_rate),
+                    slo.alert.as_deref().map_or("-".normal(), |alert| alert.red())
+                )?;
+            }
+        }
+
+        writeln!(out, "\n{}", "Performance Metrics".bold().underline())?;
+        writeln!(out, "{}: {}", "Signals sent".bold(), self.metrics.signals_sent)?;
+        writeln!(out, "{}: {}", "Signals processed".bold(), self.metrics.signals_processed)?;
+        writeln!(out, "{}: {}", "Signals failed".bold(), self.metrics.signals_failed.to_string().red())?;
+        writeln!(out, "{}: {:.2}ms", "Average latency".bold(), self.metrics.average_latency_ms)?;
+
+        if let Some(costs) = &self.costs {
+            costs.render_text(out)?;
+        }
+        Ok(())
+    }
+}
+
+// Costs
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CostReport {
+    pub daily_cost: f64,
+    pub daily_limit: f64,
+    pub total_cost: f64,
+    pub period: Option<BudgetPeriod>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BudgetPeriod {
+    pub period: String,
+    pub period_start: String,
+    pub resets_at: String,
+    pub budget: f64,
+    pub carried_over: f64,
+    pub consumed: f64,
+    pub remaining: f64,
+    pub downgraded_to: Option<String>,
+}
+
+impl Render for CostReport {
+    fn render_text(&self, out: &mut String) -> fmt::Result {
+        writeln!(out, "\n{}", "API Costs".bold().underline())?;
+        writeln!(out, "{}: ${:.2} of ${:.2}", "Today".bold(), self.daily_cost, self.daily_limit)?;
+        writeln!(out, "{}: ${:.2}", "Total".bold(), self.total_cost)?;
+
+        if let Some(period) = &self.period {
+            let available = period.budget + period.carried_over;
+            let remaining = format!("${:.2}", period.remaining);
+            let remaining = if period.remaining <= 0.0 {
+                remaining.red()
+            } else if period.remaining < available * 0.2 {
+                remaining.yellow()
+            } else {
+                remaining.green()
+            };
+
+            writeln!(out, "{}: {} since {}", "Budget period".bold(), period.period, period.period_start)?;
+            writeln!(out, "{}: ${:.2} of ${:.2} (${:.2} carried over)",
+                "Consumed".bold(),
+                period.consumed,
+                available,
+                period.carried_over
+            )?;
+            writeln!(out, "{}: {}", "Remaining".bold(), remaining)?;
+            writeln!(out, "{}: {}", "Resets at".bold(), period.resets_at)?;
+            if let Some(model) = &period.downgraded_to {
+                writeln!(out, "{}: requests downgraded to {}", "Grace mode".bold(), model.yellow())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// Signal
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SignalResult {
+    pub from: String,
+    pub to: String,
+    pub content: String,
+    /// Assigned by the server; older servers don't return one
+    pub signal_id: Option<String>,
+}
+
+impl Render for SignalResult {
+    fn render_text(&self, out: &mut String) -> fmt::Result {
+        writeln!(out, "{} signal:", "Sent".green())?;
+        writeln!(out, "  {}: {}", "From".bold(), self.from.cyan())?;
+        writeln!(out, "  {}: {}", "To".bold(), self.to.cyan())?;
+        writeln!(out, "  {}: {}", "Content".bold(), self.content)?;
+        writeln!(out, "\n{} Signal sent successfully!", "✓".green())?;
+        if let Some(signal_id) = &self.signal_id {
+            writeln!(out, "{}: {}", "Signal ID".bold(), signal_id.yellow())?;
+        }
+        Ok(())
+    }
+}
+
+/// Chain started from a template, as the server returns it
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TemplateSubmission {
+    pub chain_id: String,
+    pub template: String,
+    /// Version the content was rendered from
+    pub version: u32,
+    /// Artifacts the server stored for files too large to inline
+    #[serde(default)]
+    pub artifacts: Vec<String>,
+}
+
+impl Render for TemplateSubmission {
+    fn render_text(&self, out: &mut String) -> fmt::Result {
+        writeln!(out, "{} template {} v{}", "Submitted".green(), self.template.cyan(), self.version)?;
+        writeln!(out, "{}: {}", "Chain ID".bold(), self.chain_id.yellow())?;
+        for artifact in &self.artifacts {
+            writeln!(out, "{}: {}", "Artifact".bold(), artifact)?;
+        }
+        Ok(())
+    }
+}
+
+// Chains
+
+/// Chain started with `hal9 chain start`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ChainStarted {
+    pub chain_id: String,
+    /// Session the chain is a turn of
+    #[serde(default)]
+    pub session_id: Option<String>,
+}
+
+impl Render for ChainStarted {
+    fn render_text(&self, out: &mut String) -> fmt::Result {
+        writeln!(out, "{} chain {}", "Started".green(), self.chain_id.yellow())?;
+        if let Some(session_id) = &self.session_id {
+            writeln!(out, "{}: {} (continue with --session {})", "Session".bold(), session_id.cyan(), session_id)?;
+        }
+        Ok(())
+    }
+}
+
+/// Session as the server returns it
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SessionInfo {
+    pub id: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReplayResult {
+    pub chain_id: String,
+    pub replay_of: String,
+    pub compare_url: String,
+}
+
+impl Render for ReplayResult {
+    fn render_text(&self, out: &mut String) -> fmt::Result {
+        writeln!(out, "{} chain {} as {}", "Replayed".green(), self.replay_of.cyan(), self.chain_id.yellow())?;
+        writeln!(out, "{}: hal9 chain compare {} {}", "Compare".bold(), self.replay_of, self.chain_id)
+    }
+}
+
+/// Chain comparison as the server returns it
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ChainComparisonReport {
+    pub a: String,
+    pub b: String,
+    pub nodes: Vec<ChainNodeDiff>,
+    pub summary: ChainComparisonSummary,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ChainNodeDiff {
+    pub path: String,
+    pub layer: String,
+    /// added, removed, changed or unchanged
+    pub change: String,
+    pub a: Option<ChainNodeSide>,
+    pub b: Option<ChainNodeSide>,
+    pub content_diff: Option<String>,
+    pub tokens_delta: i64,
+    pub latency_delta_ms: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ChainNodeSide {
+    pub signal_id: String,
+    pub neuron_id: String,
+    pub model: Option<String>,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub duration_ms: i64,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ChainComparisonSummary {
+    pub nodes_added: usize,
+    pub nodes_removed: usize,
+    pub nodes_changed: usize,
+    pub nodes_unchanged: usize,
+    pub tokens_delta: i64,
+    pub cost_a: f64,
+    pub cost_b: f64,
+    pub cost_delta: f64,
+    pub duration_delta_ms: Option<i64>,
+}
+
+impl Render for ChainComparisonReport {
+    fn render_text(&self, out: &mut String) -> fmt::Result {
+        writeln!(out, "{} {} → {}", "Comparing".bold(), self.a.cyan(), self.b.cyan())?;
+        for node in self.nodes.iter().filter(|n| n.change != "unchanged") {
+            let change = match node.change.as_str() {
+                "added" => node.change.green(),
+                "removed" => node.change.red(),
+                _ => node.change.yellow(),
+            };
+            let neuron = node.b.as_ref().or(node.a.as_ref()).map(|side| side.neuron_id.as_str()).unwrap_or_default();
+            writeln!(out, "\n{:<9} {} {} (tokens {:+}, latency {:+}ms)",
+                change, node.path.bold(), neuron.dimmed(), node.tokens_delta, node.latency_delta_ms)?;
+            if let (Some(a), Some(b)) = (&node.a, &node.b) {
+                if a.model != b.model {
+                    writeln!(out, "  model: {} → {}", a.model.as_deref().unwrap_or("-"), b.model.as_deref().unwrap_or("-"))?;
+                }
+            }
+            // Skip the file headers; the path says which signal this is
+            for line in node.content_diff.iter().flat_map(|diff| diff.lines().skip(2)) {
+                let line = match line.chars().next() {
+                    Some('+') => line.green(),
+                    Some('-') => line.red(),
+                    Some('@') => line.cyan(),
+                    _ => line.normal(),
+                };
+                writeln!(out, "  {}", line)?;
+            }
+        }
+
+        let summary = &self.summary;
+        writeln!(out, "\n{} added, {} removed, {} changed, {} unchanged",
+            summary.nodes_added.to_string().green(),
+            summary.nodes_removed.to_string().red(),
+            summary.nodes_changed.to_string().yellow(),
+            summary.nodes_unchanged
+        )?;
+        write!(out, "Tokens {:+}, cost ${:.4} → ${:.4} ({:+.4})",
+            summary.tokens_delta, summary.cost_a, summary.cost_b, summary.cost_delta)?;
+        match summary.duration_delta_ms {
+            Some(delta) => writeln!(out, ", duration {:+}ms", delta),
+            None => writeln!(out),
+        }
+    }
+}
+
+// List
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ListReport {
+    pub resource: String,
+    /// Items as the server returned them, e.g. `{id, layer, state, is_healthy}` for neurons
+    pub items: Vec<Value>,
+    pub total_estimate: usize,
+    /// Cursor for the next page when not everything was fetched
+    pub next_cursor: Option<String>,
+}
+
+impl Render for ListReport {
+    fn render_text(&self, out: &mut String) -> fmt::Result {
+        for item in &self.items {
+            writeln!(out, "{}", item)?;
+        }
+        writeln!(out, "\n{} of ~{} {}", self.items.len().to_string().bold(), self.total_estimate, self.resource)?;
+        if let Some(next) = &self.next_cursor {
+            writeln!(out, "{} more available: --all, or query with cursor={}", "…".yellow(), next.cyan())?;
+        }
+        Ok(())
+    }
+}
+
+// Logs
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LogsReport {
+    pub entries: Vec<LogEntry>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LogEntry {
+    pub timestamp: String,
+    pub level: String,
+    pub target: String,
+    pub message: String,
+    pub chain_id: Option<String>,
+    pub neuron_id: Option<String>,
+    pub layer: Option<String>,
+    pub event: Option<String>,
+}
+
+impl Render for LogsReport {
+    fn render_text(&self, out: &mut String) -> fmt::Result {
+        if self.entries.is_empty() {
+            return writeln!(out, "{}", "No matching log entries".yellow());
+        }
+        for entry in &self.entries {
+            let level = match entry.level.as_str() {
+                "ERROR" => entry.level.red(),
+                "WARN" => entry.level.yellow(),
+                "INFO" => entry.level.green(),
+                _ => entry.level.normal(),
+            };
+
+            let mut context = Vec::new();
+            if let Some(neuron) = &entry.neuron_id {
+                context.push(neuron.cyan().to_string());
+            }
+            if let Some(layer) = &entry.layer {
+                context.push(layer.clone());
+            }
+            if let Some(event) = &entry.event {
+                context.push(event.blue().to_string());
+            }
+
+            writeln!(out, "{} {:<5} {} [{}] {}",
+                entry.timestamp.dimmed(),
+                level,
+                entry.target.dimmed(),
+                context.join(" "),
+                entry.message
+            )?;
+        }
+        Ok(())
+    }
+}
+
+// Memory
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MemoryExportResult {
+    pub file: String,
+    pub entries: usize,
+}
+
+impl Render for MemoryExportResult {
+    fn render_text(&self, out: &mut String) -> fmt::Result {
+        writeln!(out, "{} {} memories to {}", "Exported".green(), self.entries, self.file.cyan())
+    }
+}
+
+/// Import report as the server returns it
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MemoryImportReport {
+    pub dry_run: bool,
+    /// skip, overwrite or merge-newer
+    pub policy: String,
+    pub source_provider: String,
+    pub created: usize,
+    pub overwritten: usize,
+    pub skipped: usize,
+    pub unchanged: usize,
+    pub embeddings_recomputed: usize,
+    pub changes: Vec<MemoryChange>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MemoryChange {
+    pub key: String,
+    pub namespace: String,
+    /// create, overwrite, skip or unchanged
+    pub action: String,
+}
+
+impl Render for MemoryImportReport {
+    fn render_text(&self, out: &mut String) -> fmt::Result {
+        if self.dry_run {
+            writeln!(out, "{}", "Dry run: nothing was written".yellow())?;
+        }
+        for change in self.changes.iter().filter(|c| c.action != "unchanged") {
+            let action = match change.action.as_str() {
+                "create" => change.action.green(),
+                "overwrite" => change.action.yellow(),
+                _ => change.action.dimmed(),
+            };
+            writeln!(out, "{:<9} {} {}", action, change.key, change.namespace.dimmed())?;
+        }
+        writeln!(out, "{} created, {} overwritten, {} skipped, {} unchanged ({})",
+            self.created.to_string().bold(),
+            self.overwritten.to_string().bold(),
+            self.skipped,
+            self.unchanged,
+            self.policy
+        )?;
+        if self.embeddings_recomputed > 0 {
+            writeln!(out, "Recomputed {} embeddings from {}", self.embeddings_recomputed, self.source_provider)?;
+        }
+        Ok(())
+    }
+}
+
+// Start and stop
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ServerLifecycle {
+    pub server_id: String,
+    /// "running" once started, "stopped" after shutdown
+    pub state: String,
+    pub neurons: usize,
+}
+
+impl Render for ServerLifecycle {
+    fn render_text(&self, out: &mut String) -> fmt::Result {
+        match self.state.as_str() {
+            "running" => {
+                writeln!(out, "{} {} with {} neurons", "Server started:".green(), self.server_id.cyan(), self.neurons)?;
+                writeln!(out, "{}", "Server is running. Press Ctrl+C to stop.".blue())
+            }
+            _ => writeln!(out, "{}", "Server stopped.".green()),
+        }
+    }
+}
+
+/// Local stack started with `hal9 dev`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DevReady {
+    pub server_id: String,
+    pub url: String,
+    /// Admin UI, when the binary was built with it
+    #[serde(default)]
+    pub admin_ui: Option<String>,
+    /// Topology file watched for changes
+    pub topology: String,
+    pub neurons: usize,
+    pub users: Vec<DevUser>,
+    /// Chain of the scenario run at startup
+    #[serde(default)]
+    pub scenario: Option<DevScenario>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DevUser {
+    pub username: String,
+    pub role: String,
+    pub password: String,
+    pub api_key: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DevScenario {
+    pub name: String,
+    pub chain_id: String,
+    pub status: String,
+    /// Layers the chain went through, in order
+    pub layers: Vec<String>,
+    #[serde(default)]
+    pub final_output: Option<String>,
+}
+
+impl Render for DevReady {
+    fn render_text(&self, out: &mut String) -> fmt::Result {
+        writeln!(out, "{} {} at {} with {} neurons", "Dev server ready:".green(), self.server_id.cyan(), self.url, self.neurons)?;
+        match &self.admin_ui {
+            Some(url) => writeln!(out, "{}: {}", "Admin UI".bold(), url)?,
+            None => writeln!(out, "{}: not in this build; rebuild with the admin-ui feature", "Admin UI".bold())?,
+        }
+        writeln!(out, "{}: {} (edits are applied as they are saved)", "Topology".bold(), self.topology)?;
+        writeln!(out, "{}", "Demo users:".bold())?;
+        for user in &self.users {
+            writeln!(out, "  {:<10} {:<6} password {}  API key {}", user.username.cyan(), user.role, user.password, user.api_key.yellow())?;
+        }
+        if let Some(scenario) = &self.scenario {
+            writeln!(
+                out, "{} {}: chain {} {} through {}",
+                "Scenario".bold(), scenario.name.cyan(), scenario.chain_id.yellow(), scenario.status, scenario.layers.join(" → ")
+            )?;
+            if let Some(output) = &scenario.final_output {
+                writeln!(out, "{}", output)?;
+            }
+        }
+        writeln!(out, "{}", "Press Ctrl+C to stop.".blue())
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StopResult {
+    pub server: String,
+    pub force: bool,
+    pub stopped: bool,
+    pub message: String,
+}
+
+impl Render for StopResult {
+    fn render_text(&self, out: &mut String) -> fmt::Result {
+        if self.force {
+            writeln!(out, "{}", "Force stopping server...".red())?;
+        } else {
+            writeln!(out, "{}", "Gracefully stopping server...".yellow())?;
+        }
+        writeln!(out, "Would connect to: {}", self.server.cyan())?;
+        writeln!(out, "{}", self.message.yellow())
+    }
+}
+
+// Secrets
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SecretsResult {
+    /// "encrypted" or "rotated"
+    pub action: String,
+    pub config: String,
+    pub count: usize,
+}
+
+impl Render for SecretsResult {
+    fn render_text(&self, out: &mut String) -> fmt::Result {
+        let mut action = self.action.clone();
+        action[..1].make_ascii_uppercase();
+        writeln!(out, "{} {} secret(s) in {}", action.green(), self.count, self.config)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GeneratedKey {
+    pub key: String,
+}
+
+impl Render for GeneratedKey {
+    fn render_text(&self, out: &mut String) -> fmt::Result {
+        writeln!(out, "{}", self.key)
+    }
+}
+
+// Admin
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CompactResult {
+    pub server: String,
+    pub databases: Vec<CompactedDatabase>,
+}
+
+/// One database's compaction as the server returns it
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CompactedDatabase {
+    pub database: String,
+    pub bytes_before: i64,
+    pub bytes_after: i64,
+    pub duration_ms: u64,
+}
+
+impl Render for CompactResult {
+    fn render_text(&self, out: &mut String) -> fmt::Result {
+        if self.databases.is_empty() {
+            return writeln!(out, "{}", "No SQLite databases to compact".yellow());
+        }
+        for db in &self.databases {
+            let freed = db.bytes_before - db.bytes_after;
+            writeln!(out, "{:<12} {} -> {} bytes ({} freed) in {} ms",
+                db.database.cyan(),
+                db.bytes_before,
+                db.bytes_after,
+                freed.to_string().green(),
+                db.duration_ms
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TieringStatusResult {
+    pub server: String,
+    #[serde(flatten)]
+    pub tiers: TieringStatus,
+}
+
+/// Memory tiers as the server reports them
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TieringStatus {
+    /// Whether the tiering job runs
+    pub enabled: bool,
+    pub hot: TierSize,
+    pub cold: TierSize,
+    #[serde(default)]
+    pub cold_tier: Option<ColdTierReads>,
+    #[serde(default)]
+    pub last_run: Option<TieringRun>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TierSize {
+    pub entries: u64,
+    pub bytes: u64,
+}
+
+/// Reads served from the cold tier since the server started
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ColdTierReads {
+    pub backend: String,
+    pub reads: u64,
+    pub promotions: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TieringRun {
+    pub ran_at: String,
+    pub moved: u64,
+    pub skipped: u64,
+    pub failed: u64,
+    pub orphans_deleted: u64,
+}
+
+impl Render for TieringStatusResult {
+    fn render_text(&self, out: &mut String) -> fmt::Result {
+        let tiers = &self.tiers;
+        let backend = tiers.cold_tier.as_ref().map(|cold| cold.backend.as_str()).unwrap_or("none");
+        writeln!(out, "{:<6} {} entries, {} bytes", "hot".green(), tiers.hot.entries, tiers.hot.bytes)?;
+        writeln!(out, "{:<6} {} entries, {} bytes ({})", "cold".cyan(), tiers.cold.entries, tiers.cold.bytes, backend)?;
+        if let Some(cold) = &tiers.cold_tier {
+            writeln!(out, "Cold reads: {}, promoted back: {}", cold.reads, cold.promotions)?;
+        }
+        match &tiers.last_run {
+            Some(run) => writeln!(out, "Last run {}: {} moved, {} skipped, {} failed, {} orphaned objects deleted",
+                run.ran_at, run.moved, run.skipped, run.failed, run.orphans_deleted
+            )?,
+            None => writeln!(out, "No tiering run yet")?,
+        }
+        if !tiers.enabled {
+            writeln!(out, "{}", "The tiering job is disabled".yellow())?;
+        }
+        Ok(())
+    }
+}
+
+// Database
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SchemaReport {
+    pub database: String,
+    /// "current", "behind", "ahead" or "dirty"
+    pub state: String,
+    /// Newest applied migration; null on a fresh database
+    pub current: Option<i64>,
+    /// Newest migration this release carries
+    pub expected: i64,
+    pub pending: Vec<PendingMigration>,
+    /// Applied migrations from a newer release
+    pub unknown: Vec<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PendingMigration {
+    pub version: i64,
+    pub description: String,
+}
+
+impl Render for SchemaReport {
+    fn render_text(&self, out: &mut String) -> fmt::Result {
+        let current = self.current.map_or_else(|| "none".to_string(), |v| v.to_string());
+        let state = match self.state.as_str() {
+            "current" => self.state.green(),
+            "behind" => self.state.yellow(),
+            _ => self.state.red(),
+        };
+        writeln!(out, "{:<10} {}", "Database:".bold(), self.database)?;
+        writeln!(out, "{:<10} {} (expected {})", "Version:".bold(), current, self.expected)?;
+        writeln!(out, "{:<10} {}", "State:".bold(), state)?;
+        for migration in &self.pending {
+            writeln!(out, "  pending {:>4} {}", migration.version, migration.description)?;
+        }
+        if !self.unknown.is_empty() {
+            writeln!(out, "  {} applied by a newer release: {:?}", "Unknown migrations".red(), self.unknown)?;
+        }
+        Ok(())
+    }
+}
+
+// Profiles
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProfileList {
+    pub path: String,
+    /// Profile used when none is selected
+    pub default: Option<String>,
+    /// Profile selected for this invocation
+    pub active: Option<String>,
+    pub profiles: Vec<ProfileEntry>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProfileEntry {
+    pub name: String,
+    pub server: String,
+    /// Where the API key is looked up, e.g. "keyring:staging, env:HAL9_KEY";
+    /// never the key itself
+    pub api_key: Option<String>,
+    pub output: Option<String>,
+    pub org: Option<String>,
+    pub production: bool,
+}
+
+impl Render for ProfileList {
+    fn render_text(&self, out: &mut String) -> fmt::Result {
+        if self.profiles.is_empty() {
+            return writeln!(out, "{} (add one with `hal9 profile add`)", format!("No profiles in {}", self.path).yellow());
+        }
+        for profile in &self.profiles {
+            let marker = if self.active.as_ref() == Some(&profile.name) { "*" } else { " " };
+            let name = if profile.production { profile.name.red().bold() } else { profile.name.cyan() };
+            write!(out, "{} {:<16} {}", marker, name, profile.server)?;
+            if let Some(api_key) = &profile.api_key {
+                write!(out, "  key from {}", api_key)?;
+            }
+            if let Some(org) = &profile.org {
+                write!(out, "  org {}", org)?;
+            }
+            if profile.production {
+                write!(out, "  {}", "production".red())?;
+            }
+            writeln!(out)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProfileChange {
+    pub profile: String,
+    /// "added", "updated", "removed" or "default"
+    pub action: String,
+    pub path: String,
+}
+
+impl Render for ProfileChange {
+    fn render_text(&self, out: &mut String) -> fmt::Result {
+        if self.action == "default" {
+            return writeln!(out, "Using profile {} by default", self.profile.cyan());
+        }
+        let mut action = self.action.clone();
+        action[..1].make_ascii_uppercase();
+        writeln!(out, "{} profile {} in {}", action.green(), self.profile.cyan(), self.path)
+    }
+}
+
+fn format_duration(seconds: u64) -> String {
+    if seconds < 60 {
+        format!("{} seconds", seconds)
+    } else if seconds < 3600 {
+        format!("{} minutes", seconds / 60)
+    } else if seconds < 86400 {
+        format!("{:.1} hours", seconds as f64 / 3600.0)
+    } else {
+        format!("{:.1} days", seconds as f64 / 86400.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    //! The JSON below is the public schema. If one of these fails, the change
+    //! breaks scripts built on the CLI.
+
+    use super::*;
+    use serde_json::json;
+
+    fn json_of<T: Render>(report: &T) -> Value {
+        serde_json::from_str(&format(OutputFormat::Json, report).unwrap()).unwrap()
+    }
+
+    fn costs() -> CostReport {
+        CostReport {
+            daily_cost: 1.5,
+            daily_limit: 10.0,
+            total_cost: 42.0,
+            period: Some(BudgetPeriod {
+                period: "daily".to_string(),
+                period_start: "2025-01-01T00:00:00Z".to_string(),
+                resets_at: "2025-01-02T00:00:00Z".to_string(),
+                budget: 10.0,
+                carried_over: 2.0,
+                consumed: 1.5,
+                remaining: 10.5,
+                downgraded_to: None,
+            }),
+        }
+    }
+
+    #[test]
+    fn test_status_schema() {
+        let report = StatusReport {
+            server: "localhost:8080".to_string(),
+            running: true,
+            uptime_seconds: 90,
+            neurons: vec![NeuronStatus {
+                id: "neuron-1".to_string(),
+                layer: "L4".to_string(),
+                state: "Running".to_string(),
+                health: "Healthy".to_string(),
+            }],
+            metrics: MetricsSummary {
+                signals_sent: 3,
+                signals_processed: 2,
+                signals_failed: 1,
+                average_latency_ms: 12.5,
+            },
+            paused_layers: vec![PausedLayer {
+                layer: "L2".to_string(),
+                reason: Some("runaway spend".to_string()),
+                paused_by: Some("admin".to_string()),
+                paused_at: "2024-05-01T12:00:00Z".to_string(),
+                resume_at: None,
+                held: 4,
+            }],
+            read_only: Some(ReadOnlyMode {
+                active: true,
+                reason: Some("database upgrade".to_string()),
+                enabled_by: Some("admin".to_string()),
+                since: Some("2024-05-01T12:00:00Z".to_string()),
+                retry_after_secs: Some(300),
+                memory_writes: "hold".to_string(),
+                held_writes: 2,
+                skipped_writes: 0,
+                refused_requests: 7,
+                replica_reads: vec!["auth".to_string()],
+            }),
+            slos: vec![SloState {
+                name: "chain-latency".to_string(),
+                layer: None,
+                latency_ms: 5000,
+                objective: 0.99,
+                compliance: 0.95,
+                compliant: false,
+                budget_remaining: 0.0,
+                fast_burn_rate: 5.0,
+                slow_burn_rate: 5.0,
+                projected_exhaustion: Some("2024-05-01T12:00:00Z".to_string()),
+                alert: None,
+            }],
+            costs: None,
+        };
+        assert_eq!(json_of(&report), json!({
+            "server": "localhost:8080",
+            "running": true,
+            "uptime_seconds": 90,
+            "neurons": [{"id": "neuron-1", "layer": "L4", "state": "Running", "health": "Healthy"}],
+            "metrics": {
+                "signals_sent": 3,
+                "signals_processed": 2,
+                "signals_failed": 1,
+                "average_latency_ms": 12.5
+            },
+            "paused_layers": [{
+                "layer": "L2",
+                "reason": "runaway spend",
+                "paused_by": "admin",
+                "paused_at": "2024-05-01T12:00:00Z",
+                "resume_at": null,
+                "held": 4
+            }],
+            "read_only": {
+                "active": true,
+                "reason": "database upgrade",
+                "enabled_by": "admin",
+                "since": "2024-05-01T12:00:00Z",
+                "retry_after_secs": 300,
+                "memory_writes": "hold",
+                "held_writes": 2,
+                "skipped_writes": 0,
+                "refused_requests": 7,
+                "replica_reads": ["auth"]
+            },
+            "slos": [{
+                "name": "chain-latency",
+                "layer": null,
+                "latency_ms": 5000,
+                "objective": 0.99,
+                "compliance": 0.95,
+                "compliant": false,
+                "budget_remaining": 0.0,
+                "fast_burn_rate": 5.0,
+                "slow_burn_rate": 5.0,
+                "projected_exhaustion": "2024-05-01T12:00:00Z",
+                "alert": null
+            }],
+            "costs": null
+        }));
+    }
+
+    #[test]
+    fn test_cost_report_schema() {
+        assert_eq!(json_of(&costs()), json!({
+            "daily_cost": 1.5,
+            "daily_limit": 10.0,
+            "total_cost": 42.0,
+            "period": {
+                "period": "daily",
+                "period_start": "2025-01-01T00:00:00Z",
+                "resets_at": "2025-01-02T00:00:00Z",
+                "budget": 10.0,
+                "carried_over": 2.0,
+                "consumed": 1.5,
+                "remaining": 10.5,
+                "downgraded_to": null
+            }
+        }));
+    }
+
+    #[test]
+    fn test_signal_result_schema() {
+        let result = SignalResult {
+            from: "user".to_string(),
+            to: "neuron-1".to_string(),
+            content: "hello".to_string(),
+            signal_id: Some("abc".to_string()),
+        };
+        assert_eq!(json_of(&result), json!({
+            "from": "user",
+            "to": "neuron-1",
+            "content": "hello",
+            "signal_id": "abc"
+        }));
+    }
+
+    #[test]
+    fn test_neuron_list_schema() {
+        let report = ListReport {
+            resource: "neurons".to_string(),
+            items: vec![json!({"id": "neuron-1", "layer": "L4", "state": "Running", "is_healthy": true})],
+            total_estimate: 7,
+            next_cursor: Some("c2".to_string()),
+        };
+        assert_eq!(json_of(&report), json!({
+            "resource": "neurons",
+            "items": [{"id": "neuron-1", "layer": "L4", "state": "Running", "is_healthy": true}],
+            "total_estimate": 7,
+            "next_cursor": "c2"
+        }));
+    }
+
+    #[test]
+    fn test_memory_import_report_schema() {
+        let server_json = json!({
+            "dry_run": true,
+            "policy": "merge-newer",
+            "source_provider": "hal9-ngram-384",
+            "created": 1,
+            "overwritten": 0,
+            "skipped": 1,
+            "unchanged": 0,
+            "embeddings_recomputed": 0,
+            "changes": [
+                {"key": "k1", "namespace": "global", "action": "create"},
+                {"key": "k2", "namespace": "global", "action": "skip"}
+            ]
+        });
+        let report: MemoryImportReport = serde_json::from_value(server_json.clone()).unwrap();
+        assert_eq!(json_of(&report), server_json);
+
+        colored::control::set_override(false);
+        let text = format(OutputFormat::Text, &report).unwrap();
+        assert!(text.starts_with("Dry run: nothing was written"));
+        assert!(text.contains("1 created, 0 overwritten, 1 skipped, 0 unchanged (merge-newer)"));
+    }
+
+    #[test]
+    fn test_compact_result_schema() {
+        let databases = json!([
+            {"database": "memory", "bytes_before": 8192, "bytes_after": 4096, "duration_ms": 12}
+        ]);
+        let report = CompactResult {
+            server: "localhost:8080".to_string(),
+            databases: serde_json::from_value(databases.clone()).unwrap(),
+        };
+        assert_eq!(json_of(&report), json!({"server": "localhost:8080", "databases": databases}));
+
+        colored::control::set_override(false);
+        let text = format(OutputFormat::Text, &report).unwrap();
+        assert!(text.contains("8192 -> 4096 bytes (4096 freed) in 12 ms"), "{}", text);
+    }
+
+    #[test]
+    fn test_tiering_status_schema() {
+        let server_json = json!({
+            "enabled": true,
+            "hot": {"entries": 120, "bytes": 48000},
+            "cold": {"entries": 900, "bytes": 3600000},
+            "cold_tier": {"backend": "filesystem", "reads": 14, "promotions": 2},
+            "last_run": {
+                "ran_at": "2026-10-16T03:00:00Z",
+                "moved": 40,
+                "skipped": 1,
+                "failed": 0,
+                "orphans_deleted": 3
+            }
+        });
+        let report = TieringStatusResult {
+            server: "localhost:8080".to_string(),
+            tiers: serde_json::from_value(server_json.clone()).unwrap(),
+        };
+        let mut expected = server_json;
+        expected["server"] = json!("localhost:8080");
+        assert_eq!(json_of(&report), expected);
+
+        colored::control::set_override(false);
+        let text = format(OutputFormat::Text, &report).unwrap();
+        assert!(text.contains("cold   900 entries, 3600000 bytes (filesystem)"), "{}", text);
+        assert!(text.contains("40 moved, 1 skipped, 0 failed, 3 orphaned objects deleted"), "{}", text);
+    }
+
+    #[test]
+    fn test_profile_list_schema() {
+        let report = ProfileList {
+            path: "/home/ops/.config/hal9/config.toml".to_string(),
+            default: Some("staging".to_string()),
+            active: Some("production".to_string()),
+            profiles: vec![ProfileEntry {
+                name: "production".to_string(),
+                server: "https://hal9.example.com".to_string(),
+                api_key: Some("env:HAL9_PRODUCTION_KEY".to_string()),
+                output: None,
+                org: Some("platform".to_string()),
+                production: true,
+            }],
+        };
+        assert_eq!(json_of(&report), json!({
+            "path": "/home/ops/.config/hal9/config.toml",
+            "default": "staging",
+            "active": "production",
+            "profiles": [{
+                "name": "production",
+                "server": "https://hal9.example.com",
+                "api_key": "env:HAL9_PRODUCTION_KEY",
+                "output": null,
+                "org": "platform",
+                "production": true
+            }]
+        }));
+
+        colored::control::set_override(false);
+        let text = format(OutputFormat::Text, &report).unwrap();
+        assert!(text.starts_with("* production"), "{}", text);
+        assert!(text.contains("key from env:HAL9_PRODUCTION_KEY"), "{}", text);
+    }
+
+    #[test]
+    fn test_schema_report_schema() {
+        let pending = json!([{"version": 4, "description": "enterprise features"}]);
+        let report = SchemaReport {
+            database: "sqlite".to_string(),
+            state: "behind".to_string(),
+            current: Some(3),
+            expected: 4,
+            pending: serde_json::from_value(pending.clone()).unwrap(),
+            unknown: vec![],
+        };
+        assert_eq!(json_of(&report), json!({
+            "database": "sqlite",
+            "state": "behind",
+            "current": 3,
+            "expected": 4,
+            "pending": pending,
+            "unknown": []
+        }));
+
+        colored::control::set_override(false);
+        let text = format(OutputFormat::Text, &report).unwrap();
+        assert!(text.contains("3 (expected 4)"), "{}", text);
+        assert!(text.contains("pending    4 enterprise features"), "{}", text);
+    }
+
+    #[test]
+    fn test_error_schema_and_exit_codes() {
+        let error = anyhow::Error::new(CliError::unreachable("localhost:1", "connection refused"));
+        assert_eq!(exit_code(&error), 3);
+        assert_eq!(exit_code(&anyhow::anyhow!("bad config")), 1);
+
+        let report = ErrorReport {
+            error: ErrorDetail { code: exit_code(&error), message: error.to_string() },
+        };
+        assert_eq!(serde_json::to_value(&report).unwrap(), json!({
+            "error": {
+                "code": 3,
+                "message": "Failed to connect to server at localhost:1: connection refused"
+            }
+        }));
+    }
+
+    #[test]
+    fn test_dev_ready_schema() {
+        let report = DevReady {
+            server_id: "hal9-dev".to_string(),
+            url: "http://127.0.0.1:8080".to_string(),
+            admin_ui: None,
+            topology: "topology.yaml".to_string(),
+            neurons: 3,
+            users: vec![DevUser {
+                username: "admin".to_string(),
+                role: "admin".to_string(),
+                password: "hal9-dev-password".to_string(),
+                api_key: "hal9_abc".to_string(),
+            }],
+            scenario: Some(DevScenario {
+                name: "chat".to_string(),
+                chain_id: "c1".to_string(),
+                status: "completed".to_string(),
+                layers: vec!["L4".to_string(), "L3".to_string(), "L2".to_string()],
+                final_output: None,
+            }),
+        };
+        assert_eq!(json_of(&report), json!({
+            "server_id": "hal9-dev",
+            "url": "http://127.0.0.1:8080",
+            "admin_ui": null,
+            "topology": "topology.yaml",
+            "neurons": 3,
+            "users": [{"username": "admin", "role": "admin", "password": "hal9-dev-password", "api_key": "hal9_abc"}],
+            "scenario": {"name": "chat", "chain_id": "c1", "status": "completed", "layers": ["L4", "L3", "L2"], "final_output": null}
+        }));
+    }
+
+    #[test]
+    fn test_yaml_matches_json() {
+        let yaml: Value = serde_yaml::from_str(&format(OutputFormat::Yaml, &costs()).unwrap()).unwrap();
+        assert_eq!(yaml, json_of(&costs()));
+    }
+
+    #[test]
+    fn test_text_renders_from_same_struct() {
+        colored::control::set_override(false);
+        let text = format(OutputFormat::Text, &costs()).unwrap();
+        assert!(text.contains("Today: $1.50 of $10.00"));
+        assert!(text.contains("Remaining: $10.50"));
+    }
+}
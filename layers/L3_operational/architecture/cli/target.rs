@@ -0,0 +1,69 @@
+//! The server a command talks to, from its `--server` flag or the profile
+
+use anyhow::Result;
+use hal9_client::profile::{self, ProfileFile, ResolvedProfile};
+use reqwest::header::{HeaderMap, HeaderValue};
+
+use crate::output::CliError;
+
+/// Server used when neither `--server` nor a profile names one
+pub const DEFAULT_SERVER: &str = "localhost:8080";
+
+/// Header carrying the profile's API key
+const API_KEY_HEADER: &str = "X-API-Key";
+
+#[derive(Debug, Clone)]
+pub struct Target {
+    /// URL, or `host:port` for plain HTTP, as shown in reports
+    pub server: String,
+    api_key: Option<String>,
+    /// Production profile whose server this is
+    production: Option<String>,
+}
+
+impl Target {
+    /// `--server` if given, else the profile's server, else the default.
+    /// The profile's API key is only sent to the profile's own server.
+    pub fn new(server: Option<String>, profile: Option<&ResolvedProfile>, profiles: &ProfileFile) -> Self {
+        let (server, api_key) = match (server, profile) {
+            (Some(server), profile) => {
+                let api_key = profile.filter(|p| p.server == server).and_then(|p| p.api_key.clone());
+                (server, api_key)
+            }
+            (None, Some(profile)) => (profile.server.clone(), profile.api_key.clone()),
+            (None, None) => (DEFAULT_SERVER.to_string(), None),
+        };
+        let production = profiles.production_for(&server).map(str::to_string);
+        Self { server, api_key, production }
+    }
+
+    /// Let a destructive `action` proceed. Against a production server it
+    /// needs `--yes-production` as `confirmed`, or confirming on the terminal.
+    pub fn guard(&self, action: &str, confirmed: bool) -> Result<()> {
+        let Some(name) = &self.production else {
+            return Ok(());
+        };
+        profile::confirm_production_interactive(name, &self.server, action, confirmed)
+            .map_err(|e| CliError::usage(e.to_string()).into())
+    }
+
+    /// Full URL of `path`, e.g. `/api/v1/status`
+    pub fn url(&self, path: &str) -> String {
+        if self.server.contains("://") {
+            format!("{}{}", self.server.trim_end_matches('/'), path)
+        } else {
+            format!("http://{}{}", self.server, path)
+        }
+    }
+
+    /// HTTP client sending the API key, if any, with every request
+    pub fn client(&self) -> Result<reqwest::Client> {
+        let mut headers = HeaderMap::new();
+        if let Some(key) = &self.api_key {
+            let mut value = HeaderValue::from_str(key)?;
+            value.set_sensitive(true);
+            headers.insert(API_KEY_HEADER, value);
+        }
+        Ok(reqwest::Client::builder().default_headers(headers).build()?)
+    }
+}
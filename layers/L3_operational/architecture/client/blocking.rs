@@ -0,0 +1,113 @@
+//! Blocking facade over [`crate::Hal9Client`]
+//!
+//! Each client owns a single-threaded runtime and blocks on the async
+//! client's futures. Like `reqwest::blocking`, it must not be used from
+//! within an async runtime; async callers use [`crate::Hal9Client`] directly.
+
+use std::time::Duration;
+
+use hal9_api_types::{
+    BatchSubmitResponse, ChainResult, CostStats, DeadLetter, HealthSummary, ListQuery,
+    LoginResponse, NeuronInfo, Page, RefreshResponse, SubmitSignalRequest, SubmitSignalResponse,
+    UserResponse,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use tokio::runtime::{Builder, Runtime};
+
+use crate::client::ClientConfig;
+use crate::error::{ClientError, ClientResult};
+
+/// Blocking HAL9 API client
+pub struct Hal9Client {
+    inner: crate::Hal9Client,
+    runtime: Runtime,
+}
+
+impl Hal9Client {
+    pub fn new(config: ClientConfig) -> ClientResult<Self> {
+        let runtime = Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(|e| ClientError::Config(format!("failed to start runtime: {}", e)))?;
+        // reqwest registers its connection pool with the runtime it is
+        // created on
+        let inner = runtime.block_on(async { crate::Hal9Client::new(config) })?;
+        Ok(Self { inner, runtime })
+    }
+
+    pub fn config(&self) -> &ClientConfig {
+        self.inner.config()
+    }
+
+    pub fn health(&self) -> ClientResult<HealthSummary> {
+        self.runtime.block_on(self.inner.health())
+    }
+
+    pub fn submit_signal(&self, request: &SubmitSignalRequest) -> ClientResult<SubmitSignalResponse> {
+        self.runtime.block_on(self.inner.submit_signal(request))
+    }
+
+    pub fn submit_batch(&self, signals: &[SubmitSignalRequest]) -> ClientResult<BatchSubmitResponse> {
+        self.runtime.block_on(self.inner.submit_batch(signals))
+    }
+
+    pub fn chain_result(&self, chain_id: &str) -> ClientResult<ChainResult> {
+        self.runtime.block_on(self.inner.chain_result(chain_id))
+    }
+
+    pub fn wait_for_chain(&self, chain_id: &str, timeout: Duration) -> ClientResult<ChainResult> {
+        self.runtime.block_on(self.inner.wait_for_chain(chain_id, timeout))
+    }
+
+    pub fn dead_letters(&self, query: &ListQuery) -> ClientResult<Page<DeadLetter>> {
+        self.runtime.block_on(self.inner.dead_letters(query))
+    }
+
+    pub fn neurons(&self, query: &ListQuery) -> ClientResult<Page<NeuronInfo>> {
+        self.runtime.block_on(self.inner.neurons(query))
+    }
+
+    pub fn neuron(&self, neuron_id: &str) -> ClientResult<NeuronInfo> {
+        self.runtime.block_on(self.inner.neuron(neuron_id))
+    }
+
+    pub fn costs(&self) -> ClientResult<CostStats> {
+        self.runtime.block_on(self.inner.costs())
+    }
+
+    pub fn login(&self, username: &str, password: &str) -> ClientResult<LoginResponse> {
+        self.runtime.block_on(self.inner.login(username, password))
+    }
+
+    pub fn refresh(&self) -> ClientResult<RefreshResponse> {
+        self.runtime.block_on(self.inner.refresh())
+    }
+
+    pub fn profile(&self) -> ClientResult<UserResponse> {
+        self.runtime.block_on(self.inner.profile())
+    }
+
+    pub fn get<T: DeserializeOwned>(&self, path: &str, query: &[(String, String)]) -> ClientResult<T> {
+        self.runtime.block_on(self.inner.get(path, query))
+    }
+
+    pub fn post<B, T>(&self, path: &str, body: &B) -> ClientResult<T>
+    where
+        B: Serialize + ?Sized,
+        T: DeserializeOwned,
+    {
+        self.runtime.block_on(self.inner.post(path, body))
+    }
+
+    pub fn put<B, T>(&self, path: &str, body: &B) -> ClientResult<T>
+    where
+        B: Serialize + ?Sized,
+        T: DeserializeOwned,
+    {
+        self.runtime.block_on(self.inner.put(path, body))
+    }
+
+    pub fn delete<T: DeserializeOwned>(&self, path: &str, query: &[(String, String)]) -> ClientResult<T> {
+        self.runtime.block_on(self.inner.delete(path, query))
+    }
+}
@@ -0,0 +1,510 @@
+//! Async client
+
+use std::fmt;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use hal9_api_types::{
+    ApiResponse, BatchSubmitRequest, BatchSubmitResponse, ChainResult, ChainStatus, CostStats,
+    DeadLetter, HealthSummary, ListQuery, LoginRequest, LoginResponse, NeuronInfo, Page,
+    RefreshRequest, RefreshResponse, StreamFilter, SubmitSignalRequest, SubmitSignalResponse,
+    TokenPair, UserResponse, IDEMPOTENCY_KEY_HEADER,
+};
+use rand::Rng;
+use reqwest::{header, Method, StatusCode};
+use serde::{de::DeserializeOwned, Serialize};
+use tokio::sync::Mutex;
+use tracing::debug;
+
+use crate::error::{ClientError, ClientResult};
+use crate::stream::SignalStream;
+
+/// Header carrying an API key
+const API_KEY_HEADER: &str = "X-API-Key";
+
+/// Access tokens are refreshed this long before they expire
+const REFRESH_MARGIN: Duration = Duration::from_secs(30);
+
+/// Interval between chain result polls in [`Hal9Client::wait_for_chain`]
+const CHAIN_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// How the client authenticates
+#[derive(Clone, Default)]
+pub enum Credentials {
+    #[default]
+    None,
+    /// Sent as `X-API-Key` on every request
+    ApiKey(String),
+    /// Logged in on first use; logs in again when the refresh token is
+    /// rejected
+    Password { username: String, password: String },
+    /// Tokens obtained elsewhere, refreshed with the refresh token
+    Tokens(TokenPair),
+}
+
+impl fmt::Debug for Credentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Credentials::None => write!(f, "None"),
+            Credentials::ApiKey(_) => write!(f, "ApiKey(***)"),
+            Credentials::Password { username, .. } => write!(f, "Password({}, ***)", username),
+            Credentials::Tokens(_) => write!(f, "Tokens(***)"),
+        }
+    }
+}
+
+/// Client configuration
+#[derive(Debug, Clone)]
+pub struct ClientConfig {
+    /// Server URL, e.g. `http://localhost:8080`
+    pub base_url: String,
+    pub credentials: Credentials,
+    /// Per-attempt request timeout
+    pub timeout: Duration,
+    /// Retries after the first attempt of a retryable request
+    pub max_retries: u32,
+    /// Delay before the first retry, doubled for each further one
+    pub initial_backoff: Duration,
+    /// Longest delay between attempts, including server `retry_after` hints
+    pub max_backoff: Duration,
+}
+
+impl ClientConfig {
+    pub fn new(base_url: impl Into<String>) -> Self {
+        Self {
+            base_url: base_url.into(),
+            credentials: Credentials::None,
+            timeout: Duration::from_secs(30),
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(200),
+            max_backoff: Duration::from_secs(10),
+        }
+    }
+
+    pub fn credentials(mut self, credentials: Credentials) -> Self {
+        self.credentials = credentials;
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    pub fn backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.initial_backoff = initial;
+        self.max_backoff = max;
+        self
+    }
+
+    /// Delay before retry number `attempt` (0-based), with 10% jitter
+    pub(crate) fn backoff_for(&self, attempt: u32, retry_after: Option<Duration>) -> Duration {
+        let exponential = self.initial_backoff.saturating_mul(2u32.saturating_pow(attempt));
+        let delay = retry_after.map_or(exponential, |hint| hint.max(exponential));
+        let jitter = rand::thread_rng().gen_range(0.0..=0.1);
+        delay.mul_f64(1.0 + jitter).min(self.max_backoff)
+    }
+}
+
+/// Tokens of a logged-in session
+struct Session {
+    access_token: String,
+    refresh_token: String,
+    expires_at: Instant,
+}
+
+impl Session {
+    fn new(tokens: TokenPair) -> Self {
+        Self {
+            expires_at: expiry(tokens.expires_in),
+            access_token: tokens.access_token,
+            refresh_token: tokens.refresh_token,
+        }
+    }
+}
+
+fn expiry(expires_in: i64) -> Instant {
+    Instant::now() + Duration::from_secs(expires_in.max(0) as u64)
+}
+
+/// How a request may be repeated after a failure
+#[derive(Clone, Copy, PartialEq)]
+enum Retry {
+    /// Repeating the request has no further effect
+    Safe,
+    /// Only repeated if it never reached the server
+    ConnectOnly,
+}
+
+struct Inner {
+    http: reqwest::Client,
+    /// Without a total timeout, for long-lived streams
+    stream_http: reqwest::Client,
+    config: ClientConfig,
+    session: Mutex<Option<Session>>,
+}
+
+/// Async HAL9 API client; cheap to clone
+#[derive(Clone)]
+pub struct Hal9Client {
+    inner: Arc<Inner>,
+}
+
+impl Hal9Client {
+    pub fn new(mut config: ClientConfig) -> ClientResult<Self> {
+        config.base_url = config.base_url.trim_end_matches('/').to_string();
+        if !config.base_url.starts_with("http://") && !config.base_url.starts_with("https://") {
+            return Err(ClientError::Config(format!("base URL must be http(s): {}", config.base_url)));
+        }
+
+        let http = reqwest::Client::builder()
+            .timeout(config.timeout)
+            .user_agent(concat!("hal9-client/", env!("CARGO_PKG_VERSION")))
+            .build()?;
+        let stream_http = reqwest::Client::builder()
+            .connect_timeout(config.timeout)
+            .user_agent(concat!("hal9-client/", env!("CARGO_PKG_VERSION")))
+            .build()?;
+
+        let session = match &config.credentials {
+            Credentials::Tokens(tokens) => Some(Session::new(tokens.clone())),
+            _ => None,
+        };
+
+        Ok(Self {
+            inner: Arc::new(Inner {
+                http,
+                stream_http,
+                config,
+                session: Mutex::new(session),
+            }),
+        })
+    }
+
+    pub fn config(&self) -> &ClientConfig {
+        &self.inner.config
+    }
+
+    // Health
+
+    /// Server health. Unhealthy servers answer 503 with the same body, which
+    /// is returned rather than treated as an error.
+    pub async fn health(&self) -> ClientResult<HealthSummary> {
+        let response = self.inner.http.get(self.url("/health")).send().await?;
+        match response.status() {
+            StatusCode::OK | StatusCode::SERVICE_UNAVAILABLE => decode(response).await,
+            _ => Err(ClientError::from_response(response).await),
+        }
+    }
+
+    // Signals and chains
+
+    /// Submit a signal, starting a chain. Retries reuse one idempotency key,
+    /// so the chain is started at most once.
+    pub async fn submit_signal(&self, request: &SubmitSignalRequest) -> ClientResult<SubmitSignalResponse> {
+        self.call(Method::POST, "/api/v1/signal", &[], Some(request), true).await
+    }
+
+    /// Submit several signals in one request; see
+    /// [`BatchSubmitResponse`] for per-signal outcomes
+    pub async fn submit_batch(&self, signals: &[SubmitSignalRequest]) -> ClientResult<BatchSubmitResponse> {
+        let request = BatchSubmitRequest { signals: signals.to_vec() };
+        self.call(Method::POST, "/api/v1/signals/batch", &[], Some(&request), true).await
+    }
+
+    pub async fn chain_result(&self, chain_id: &str) -> ClientResult<ChainResult> {
+        self.call::<(), _>(Method::GET, &format!("/api/v1/chains/{}", chain_id), &[], None, false).await
+    }
+
+    /// Poll a chain until it is no longer running
+    pub async fn wait_for_chain(&self, chain_id: &str, timeout: Duration) -> ClientResult<ChainResult> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let result = self.chain_result(chain_id).await?;
+            if result.status != ChainStatus::Running {
+                return Ok(result);
+            }
+            if Instant::now() + CHAIN_POLL_INTERVAL > deadline {
+                return Err(ClientError::Timeout(format!("chain {}", chain_id)));
+            }
+            tokio::time::sleep(CHAIN_POLL_INTERVAL).await;
+        }
+    }
+
+    /// Follow signal and neuron events as they happen, starting with the
+    /// next one. Reads server-sent events, which pass proxies that block
+    /// WebSocket upgrades, and reconnects where the stream left off.
+    pub fn stream_signals(&self, filter: StreamFilter) -> SignalStream {
+        SignalStream::new(self.clone(), filter)
+    }
+
+    /// Signals no neuron would take
+    pub async fn dead_letters(&self, query: &ListQuery) -> ClientResult<Page<DeadLetter>> {
+        let pairs = query.to_pairs();
+        self.call::<(), _>(Method::GET, "/api/v1/dead-letters", &pairs, None, false).await
+    }
+
+    // Neurons
+
+    pub async fn neurons(&self, query: &ListQuery) -> ClientResult<Page<NeuronInfo>> {
+        let pairs = query.to_pairs();
+        self.call::<(), _>(Method::GET, "/api/v1/neurons", &pairs, None, false).await
+    }
+
+    pub async fn neuron(&self, neuron_id: &str) -> ClientResult<NeuronInfo> {
+        self.call::<(), _>(Method::GET, &format!("/api/v1/neurons/{}", neuron_id), &[], None, false).await
+    }
+
+    // Costs
+
+    pub async fn costs(&self) -> ClientResult<CostStats> {
+        self.call::<(), _>(Method::GET, "/api/v1/costs", &[], None, false).await
+    }
+
+    // Auth
+
+    /// Log in and use the issued tokens for further requests
+    pub async fn login(&self, username: &str, password: &str) -> ClientResult<LoginResponse> {
+        let mut session = self.inner.session.lock().await;
+        let response = self.login_request(username, password).await?;
+        *session = Some(Session::new(response.tokens.clone()));
+        Ok(response)
+    }
+
+    /// Exchange the refresh token for a new access token
+    pub async fn refresh(&self) -> ClientResult<RefreshResponse> {
+        let mut session = self.inner.session.lock().await;
+        let current = session
+            .as_mut()
+            .ok_or_else(|| ClientError::Config("no session to refresh; log in first".to_string()))?;
+        let response = self.refresh_request(&current.refresh_token).await?;
+        current.access_token = response.access_token.clone();
+        current.expires_at = expiry(response.expires_in);
+        Ok(response)
+    }
+
+    /// Profile of the authenticated user
+    pub async fn profile(&self) -> ClientResult<UserResponse> {
+        self.request::<(), _>(Method::GET, "/api/v1/auth/profile", &[], None, Retry::Safe, None).await
+    }
+
+    // Endpoints outside the typed surface
+
+    /// GET a path answering with a plain (not enveloped) JSON body
+    pub async fn get<T: DeserializeOwned>(&self, path: &str, query: &[(String, String)]) -> ClientResult<T> {
+        self.request::<(), _>(Method::GET, path, query, None, Retry::Safe, None).await
+    }
+
+    /// POST to a path answering with a plain JSON body. Only retried when
+    /// the request never reached the server.
+    pub async fn post<B, T>(&self, path: &str, body: &B) -> ClientResult<T>
+    where
+        B: Serialize + ?Sized,
+        T: DeserializeOwned,
+    {
+        self.request(Method::POST, path, &[], Some(body), Retry::ConnectOnly, None).await
+    }
+
+    pub async fn put<B, T>(&self, path: &str, body: &B) -> ClientResult<T>
+    where
+        B: Serialize + ?Sized,
+        T: DeserializeOwned,
+    {
+        self.request(Method::PUT, path, &[], Some(body), Retry::Safe, None).await
+    }
+
+    pub async fn delete<T: DeserializeOwned>(&self, path: &str, query: &[(String, String)]) -> ClientResult<T> {
+        self.request::<(), _>(Method::DELETE, path, query, None, Retry::Safe, None).await
+    }
+
+    // Plumbing
+
+    fn url(&self, path: &str) -> String {
+        format!("{}{}", self.inner.config.base_url, path)
+    }
+
+    /// GET request for a streaming endpoint, not yet authorized
+    pub(crate) fn stream_request(&self, path: &str) -> reqwest::RequestBuilder {
+        self.inner.stream_http.get(self.url(path))
+    }
+
+    /// Request an `/api/v1` endpoint and unwrap its `ApiResponse` envelope
+    async fn call<B, T>(
+        &self,
+        method: Method,
+        path: &str,
+        query: &[(String, String)],
+        body: Option<&B>,
+        idempotent_submit: bool,
+    ) -> ClientResult<T>
+    where
+        B: Serialize + ?Sized,
+        T: DeserializeOwned,
+    {
+        let idempotency_key = idempotent_submit.then(|| uuid::Uuid::new_v4().to_string());
+        let envelope: ApiResponse<T> = self
+            .request(method, path, query, body, Retry::Safe, idempotency_key.as_deref())
+            .await?;
+        match envelope {
+            ApiResponse { success: true, data: Some(data), .. } => Ok(data),
+            ApiResponse { error, .. } => Err(ClientError::Rejected(
+                error.unwrap_or_else(|| "response carried no data".to_string()),
+            )),
+        }
+    }
+
+    async fn request<B, T>(
+        &self,
+        method: Method,
+        path: &str,
+        query: &[(String, String)],
+        body: Option<&B>,
+        retry: Retry,
+        idempotency_key: Option<&str>,
+    ) -> ClientResult<T>
+    where
+        B: Serialize + ?Sized,
+        T: DeserializeOwned,
+    {
+        let config = &self.inner.config;
+        let mut attempt = 0;
+        let mut reauthenticated = false;
+
+        loop {
+            let mut builder = self.inner.http.request(method.clone(), self.url(path));
+            if !query.is_empty() {
+                builder = builder.query(query);
+            }
+            if let Some(body) = body {
+                builder = builder.json(body);
+            }
+            if let Some(key) = idempotency_key {
+                builder = builder.header(IDEMPOTENCY_KEY_HEADER, key);
+            }
+            builder = self.authorize(builder).await?;
+
+            let error = match builder.send().await {
+                Ok(response) if response.status().is_success() => return decode(response).await,
+                Ok(response) => ClientError::from_response(response).await,
+                Err(e) => ClientError::Transport(e),
+            };
+
+            // A rejected access token is refreshed once per request
+            if error.status() == Some(401) && !reauthenticated && self.can_reauthenticate().await {
+                reauthenticated = true;
+                self.reauthenticate().await?;
+                continue;
+            }
+
+            let retryable = match retry {
+                Retry::Safe => error.is_retryable(),
+                Retry::ConnectOnly => matches!(&error, ClientError::Transport(e) if e.is_connect()),
+            };
+            if !retryable || attempt >= config.max_retries {
+                return Err(error);
+            }
+
+            let delay = config.backoff_for(attempt, error.retry_after());
+            debug!("{} {} failed ({}), retrying in {:?}", method, path, error, delay);
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+
+    /// Whether a rejected request can be retried with new tokens
+    async fn can_reauthenticate(&self) -> bool {
+        matches!(self.inner.config.credentials, Credentials::Password { .. })
+            || self.inner.session.lock().await.is_some()
+    }
+
+    /// Attach credentials, logging in or refreshing the access token first
+    /// if needed
+    pub(crate) async fn authorize(&self, builder: reqwest::RequestBuilder) -> ClientResult<reqwest::RequestBuilder> {
+        if let Credentials::ApiKey(key) = &self.inner.config.credentials {
+            return Ok(builder.header(API_KEY_HEADER, key));
+        }
+
+        let mut session = self.inner.session.lock().await;
+        match session.as_ref().map(|current| current.expires_at <= Instant::now() + REFRESH_MARGIN) {
+            Some(true) => self.renew(&mut session).await?,
+            Some(false) => {}
+            None => {
+                if let Credentials::Password { username, password } = &self.inner.config.credentials {
+                    let response = self.login_request(username, password).await?;
+                    *session = Some(Session::new(response.tokens));
+                }
+            }
+        }
+
+        Ok(match session.as_ref() {
+            Some(current) => builder.header(header::AUTHORIZATION, format!("Bearer {}", current.access_token)),
+            None => builder,
+        })
+    }
+
+    /// Replace the access token after the server rejected it
+    async fn reauthenticate(&self) -> ClientResult<()> {
+        let mut session = self.inner.session.lock().await;
+        self.renew(&mut session).await
+    }
+
+    /// Refresh the access token, logging in again if the refresh token is
+    /// rejected and a password is configured
+    async fn renew(&self, session: &mut Option<Session>) -> ClientResult<()> {
+        if let Some(current) = session.as_mut() {
+            match self.refresh_request(&current.refresh_token).await {
+                Ok(response) => {
+                    current.access_token = response.access_token;
+                    current.expires_at = expiry(response.expires_in);
+                    return Ok(());
+                }
+                Err(e) if e.status() != Some(401) => return Err(e),
+                Err(e) => {
+                    if !matches!(self.inner.config.credentials, Credentials::Password { .. }) {
+                        return Err(e);
+                    }
+                }
+            }
+        }
+
+        if let Credentials::Password { username, password } = &self.inner.config.credentials {
+            let response = self.login_request(username, password).await?;
+            *session = Some(Session::new(response.tokens));
+        }
+        Ok(())
+    }
+
+    async fn login_request(&self, username: &str, password: &str) -> ClientResult<LoginResponse> {
+        let request = LoginRequest {
+            username: username.to_string(),
+            password: password.to_string(),
+        };
+        self.auth_request("/api/v1/auth/login", &request).await
+    }
+
+    async fn refresh_request(&self, refresh_token: &str) -> ClientResult<RefreshResponse> {
+        let request = RefreshRequest { refresh_token: refresh_token.to_string() };
+        self.auth_request("/api/v1/auth/refresh", &request).await
+    }
+
+    /// Unauthenticated POST to an auth endpoint; bypasses `authorize`, which
+    /// holds the session lock while calling it
+    async fn auth_request<B: Serialize, T: DeserializeOwned>(&self, path: &str, body: &B) -> ClientResult<T> {
+        let response = self.inner.http.post(self.url(path)).json(body).send().await?;
+        if response.status().is_success() {
+            decode(response).await
+        } else {
+            Err(ClientError::from_response(response).await)
+        }
+    }
+}
+
+async fn decode<T: DeserializeOwned>(response: reqwest::Response) -> ClientResult<T> {
+    let body = response.bytes().await?;
+    serde_json::from_slice(&body).map_err(|e| ClientError::Decode(e.to_string()))
+}
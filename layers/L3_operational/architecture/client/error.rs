@@ -0,0 +1,124 @@
+//! Client errors
+
+use std::time::Duration;
+
+use hal9_api_types::{ApiResponse, ErrorCode, ErrorResponse};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum ClientError {
+    #[error("Invalid configuration: {0}")]
+    Config(String),
+
+    #[error("Request failed: {0}")]
+    Transport(#[from] reqwest::Error),
+
+    /// The server answered with an error status
+    #[error("{status} {code}: {message}")]
+    Api {
+        status: u16,
+        code: ErrorCode,
+        message: String,
+        /// How long the server asked to wait before retrying
+        retry_after: Option<Duration>,
+        /// ID to look the failure up under `/api/v1/errors/:id`
+        error_id: Option<String>,
+    },
+
+    /// The server accepted the request but reported `success: false`
+    #[error("Rejected: {0}")]
+    Rejected(String),
+
+    #[error("Unexpected response: {0}")]
+    Decode(String),
+
+    #[error("Timed out waiting for {0}")]
+    Timeout(String),
+}
+
+pub type ClientResult<T> = Result<T, ClientError>;
+
+impl ClientError {
+    /// Error code of an API error
+    pub fn code(&self) -> Option<&ErrorCode> {
+        match self {
+            ClientError::Api { code, .. } => Some(code),
+            _ => None,
+        }
+    }
+
+    /// HTTP status of an API error
+    pub fn status(&self) -> Option<u16> {
+        match self {
+            ClientError::Api { status, .. } => Some(*status),
+            _ => None,
+        }
+    }
+
+    /// Whether sending the same request again may succeed. Transport errors
+    /// count only when the request never reached the server or timed out.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            ClientError::Api { code, .. } => code.is_retryable(),
+            ClientError::Transport(e) => e.is_connect() || e.is_timeout(),
+            _ => false,
+        }
+    }
+
+    pub(crate) fn retry_after(&self) -> Option<Duration> {
+        match self {
+            ClientError::Api { retry_after, .. } => *retry_after,
+            _ => None,
+        }
+    }
+
+    /// Error for a non-success response. The server answers in one of three
+    /// shapes: the error taxonomy body, the `ApiResponse` envelope, or the
+    /// `{error, message}` body of the auth endpoints; anything else is kept
+    /// as text.
+    pub(crate) async fn from_response(response: reqwest::Response) -> Self {
+        let status = response.status().as_u16();
+        let header_retry_after = response
+            .headers()
+            .get(reqwest::header::RETRY_AFTER)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.trim().parse::<u64>().ok());
+        let body = response.text().await.unwrap_or_default();
+
+        let mut code = ErrorCode::from_status(status);
+        let mut retry_after = header_retry_after;
+        let mut error_id = None;
+        let message = if let Ok(error) = serde_json::from_str::<ErrorResponse>(&body) {
+            code = error.error.error_code();
+            retry_after = retry_after.or(error.retry_after);
+            error_id = error.error.error_id;
+            error.error.message
+        } else if let Some(error) = serde_json::from_str::<ApiResponse<serde_json::Value>>(&body)
+            .ok()
+            .and_then(|envelope| envelope.error)
+        {
+            error
+        } else if let Some(message) = serde_json::from_str::<serde_json::Value>(&body)
+            .ok()
+            .and_then(|value| value.get("message")?.as_str().map(str::to_string))
+        {
+            message
+        } else if body.is_empty() {
+            reqwest::StatusCode::from_u16(status)
+                .ok()
+                .and_then(|s| s.canonical_reason())
+                .unwrap_or("error")
+                .to_string()
+        } else {
+            body
+        };
+
+        ClientError::Api {
+            status,
+            code,
+            message,
+            retry_after: retry_after.map(Duration::from_secs),
+            error_id,
+        }
+    }
+}
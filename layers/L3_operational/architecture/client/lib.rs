@@ -0,0 +1,48 @@
+//! Typed client for the HAL9 HTTP API
+//!
+//! [`Hal9Client`] covers the public API surface (signals, batches, chain
+//! results, neurons, costs, dead letters, health and auth) with request and
+//! response types from `hal9-api-types`, the same ones the server
+//! serializes. On top of plain HTTP it:
+//!
+//! - authenticates with an API key, or with a username/password or token
+//!   pair whose access token is refreshed before it expires and again if the
+//!   server rejects it
+//! - sends an idempotency key with every signal submission, reused across
+//!   retries of that submission
+//! - retries requests failing with a retryable error code (rate limited,
+//!   upstream errors, unavailable), honouring `retry_after`
+//! - follows the signal stream over server-sent events, resuming after
+//!   dropped connections without losing events
+//!
+//! ```no_run
+//! # async fn run() -> hal9_client::ClientResult<()> {
+//! use hal9_client::{ClientConfig, Credentials, Hal9Client, SubmitSignalRequest};
+//!
+//! let client = Hal9Client::new(
+//!     ClientConfig::new("http://localhost:8080").credentials(Credentials::ApiKey("hal9_...".into())),
+//! )?;
+//! let submitted = client.submit_signal(&SubmitSignalRequest::new("L4", "Plan the release")).await?;
+//! let result = client.wait_for_chain(&submitted.chain_id, std::time::Duration::from_secs(60)).await?;
+//! println!("{:?}", result.final_output);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! With the `blocking` feature (on by default), [`blocking::Hal9Client`]
+//! offers the same methods for synchronous callers.
+//!
+//! [`profile`] reads the named server profiles the command line tools
+//! share.
+
+#[cfg(feature = "blocking")]
+pub mod blocking;
+pub mod client;
+pub mod error;
+pub mod profile;
+pub mod stream;
+
+pub use client::{ClientConfig, Credentials, Hal9Client};
+pub use error::{ClientError, ClientResult};
+pub use stream::{SignalStream, StreamItem};
+pub use hal9_api_types::*;
@@ -0,0 +1,313 @@
+//! Named server profiles shared by the HAL9 command line tools
+//!
+//! `hal9`, `hal9-migrate` and `hal9-codegen` read the same file,
+//! `~/.config/hal9/config.toml` (under `$XDG_CONFIG_HOME` when set):
+//!
+//! ```toml
+//! default = "staging"
+//!
+//! [profiles.staging]
+//! server = "https://staging.hal9.example.com"
+//! api_key = { keyring = "staging", env = "HAL9_STAGING_KEY" }
+//! output = "json"
+//! org = "platform"
+//!
+//! [profiles.production]
+//! server = "https://hal9.example.com"
+//! api_key = { env = "HAL9_PRODUCTION_KEY" }
+//! production = true
+//! ```
+//!
+//! The profile used is the one named by `--profile`, else by `HAL9_PROFILE`,
+//! else the file's `default`, else none. A command's own flags win over the
+//! profile, which wins over the built-in defaults.
+//!
+//! API keys are looked up in the system keyring (service `hal9`) first,
+//! then in the named environment variable. A plaintext `value` is only read
+//! when the file sets `allow_plaintext_keys = true`.
+
+use std::collections::BTreeMap;
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// Environment variable selecting a profile when `--profile` is not given
+pub const PROFILE_ENV: &str = "HAL9_PROFILE";
+
+/// Keyring service API keys are stored under
+pub const KEYRING_SERVICE: &str = "hal9";
+
+#[derive(Debug, Error)]
+pub enum ProfileError {
+    #[error("Cannot read {path}: {source}")]
+    Io { path: PathBuf, source: io::Error },
+
+    #[error("Invalid profile file {path}: {message}")]
+    Parse { path: PathBuf, message: String },
+
+    #[error("Unknown profile '{0}'")]
+    Unknown(String),
+
+    #[error("Profile '{profile}': {message}")]
+    ApiKey { profile: String, message: String },
+
+    #[error("Keyring unavailable: {0}")]
+    Keyring(String),
+
+    /// A destructive command against a production profile was not confirmed
+    #[error("Refusing to {action} on production profile '{profile}'; confirm interactively or pass --yes-production")]
+    ProductionUnconfirmed { profile: String, action: String },
+}
+
+pub type ProfileResult<T> = Result<T, ProfileError>;
+
+/// Where an API key is found. Both lookups may be set: the keyring is
+/// tried first, then the environment variable.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ApiKeyRef {
+    /// Environment variable holding the key
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub env: Option<String>,
+    /// Keyring entry under the `hal9` service
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keyring: Option<String>,
+    /// The key itself, only read with `allow_plaintext_keys`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<String>,
+}
+
+/// One environment's settings
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Profile {
+    /// Server URL, or `host:port` for plain HTTP
+    pub server: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_key: Option<ApiKeyRef>,
+    /// Default output format, e.g. `json`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output: Option<String>,
+    /// Default organization
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub org: Option<String>,
+    /// Destructive commands need confirming
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub production: bool,
+}
+
+/// The profile file
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProfileFile {
+    /// Profile used when none is selected, set by `hal9 profile use`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default: Option<String>,
+    /// Read keys written into the file itself
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub allow_plaintext_keys: bool,
+    #[serde(default)]
+    pub profiles: BTreeMap<String, Profile>,
+}
+
+/// A selected profile with its API key looked up
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvedProfile {
+    pub name: String,
+    pub server: String,
+    pub api_key: Option<String>,
+    pub output: Option<String>,
+    pub org: Option<String>,
+    pub production: bool,
+}
+
+/// Where API keys are kept outside the profile file
+pub trait SecretStore {
+    /// The secret stored under `entry`, if any
+    fn get(&self, entry: &str) -> ProfileResult<Option<String>>;
+}
+
+/// The operating system's keyring
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemKeyring;
+
+impl SecretStore for SystemKeyring {
+    #[cfg(feature = "keyring")]
+    fn get(&self, entry: &str) -> ProfileResult<Option<String>> {
+        let entry = keyring::Entry::new(KEYRING_SERVICE, entry).map_err(|e| ProfileError::Keyring(e.to_string()))?;
+        match entry.get_password() {
+            Ok(secret) => Ok(Some(secret)),
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(e) => Err(ProfileError::Keyring(e.to_string())),
+        }
+    }
+
+    #[cfg(not(feature = "keyring"))]
+    fn get(&self, _entry: &str) -> ProfileResult<Option<String>> {
+        Err(ProfileError::Keyring("built without the keyring feature".to_string()))
+    }
+}
+
+/// `~/.config/hal9/config.toml`, or under `$XDG_CONFIG_HOME` when set
+pub fn default_path() -> Option<PathBuf> {
+    let base = match std::env::var_os("XDG_CONFIG_HOME").filter(|dir| !dir.is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+    };
+    Some(base.join("hal9").join("config.toml"))
+}
+
+impl ProfileFile {
+    /// Read the file at `path`; a missing file holds no profiles
+    pub fn load(path: &Path) -> ProfileResult<Self> {
+        let text = match std::fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(source) => return Err(ProfileError::Io { path: path.to_path_buf(), source }),
+        };
+        toml::from_str(&text).map_err(|e| ProfileError::Parse { path: path.to_path_buf(), message: e.to_string() })
+    }
+
+    /// Read the file at [`default_path`]
+    pub fn load_default() -> ProfileResult<Self> {
+        match default_path() {
+            Some(path) => Self::load(&path),
+            None => Ok(Self::default()),
+        }
+    }
+
+    /// Write the file to `path`, creating its directory
+    pub fn save(&self, path: &Path) -> ProfileResult<()> {
+        let io_error = |source| ProfileError::Io { path: path.to_path_buf(), source };
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(io_error)?;
+        }
+        let text = toml::to_string_pretty(self)
+            .map_err(|e| ProfileError::Parse { path: path.to_path_buf(), message: e.to_string() })?;
+        std::fs::write(path, text).map_err(io_error)
+    }
+
+    /// Name of the profile to use: `flag`, else `HAL9_PROFILE` as `env`
+    /// reports it, else the default
+    pub fn selected(&self, flag: Option<&str>, env: &dyn Fn(&str) -> Option<String>) -> Option<String> {
+        flag.map(str::to_string)
+            .or_else(|| env(PROFILE_ENV).filter(|name| !name.is_empty()))
+            .or_else(|| self.default.clone())
+    }
+
+    /// Select a profile as [`ProfileFile::selected`] does and look its API
+    /// key up. Naming a profile the file lacks is an error.
+    pub fn resolve(
+        &self,
+        flag: Option<&str>,
+        env: &dyn Fn(&str) -> Option<String>,
+        secrets: &dyn SecretStore,
+    ) -> ProfileResult<Option<ResolvedProfile>> {
+        let Some(name) = self.selected(flag, env) else {
+            return Ok(None);
+        };
+        let profile = self.profiles.get(&name).ok_or_else(|| ProfileError::Unknown(name.clone()))?;
+        let api_key = match &profile.api_key {
+            Some(reference) => self.api_key(&name, reference, env, secrets)?,
+            None => None,
+        };
+        Ok(Some(ResolvedProfile {
+            name,
+            server: profile.server.clone(),
+            api_key,
+            output: profile.output.clone(),
+            org: profile.org.clone(),
+            production: profile.production,
+        }))
+    }
+
+    /// Name of a production profile whose server is `server`, so that
+    /// destructive commands are guarded even when the production URL is
+    /// passed with `--server` under another profile
+    pub fn production_for(&self, server: &str) -> Option<&str> {
+        let server = server.trim_end_matches('/');
+        self.profiles.iter()
+            .find(|(_, profile)| profile.production && profile.server.trim_end_matches('/') == server)
+            .map(|(name, _)| name.as_str())
+    }
+
+    /// Resolve the profile selected from the flag and the process
+    /// environment, with keys from the system keyring
+    pub fn resolve_from_env(&self, flag: Option<&str>) -> ProfileResult<Option<ResolvedProfile>> {
+        self.resolve(flag, &|name| std::env::var(name).ok(), &SystemKeyring)
+    }
+
+    /// The keyring entry if it holds the key, else the environment variable.
+    /// An unusable keyring only fails the lookup when there is no variable
+    /// to fall back to.
+    fn api_key(
+        &self,
+        profile: &str,
+        reference: &ApiKeyRef,
+        env: &dyn Fn(&str) -> Option<String>,
+        secrets: &dyn SecretStore,
+    ) -> ProfileResult<Option<String>> {
+        let mut keyring_error = None;
+        if let Some(entry) = &reference.keyring {
+            match secrets.get(entry) {
+                Ok(Some(key)) => return Ok(Some(key)),
+                Ok(None) => {}
+                Err(e) => keyring_error = Some(e),
+            }
+        }
+        if let Some(key) = reference.env.as_deref().and_then(env).filter(|key| !key.is_empty()) {
+            return Ok(Some(key));
+        }
+        if let Some(key) = &reference.value {
+            if !self.allow_plaintext_keys {
+                return Err(ProfileError::ApiKey {
+                    profile: profile.to_string(),
+                    message: "plaintext API keys need allow_plaintext_keys = true".to_string(),
+                });
+            }
+            return Ok(Some(key.clone()));
+        }
+        match keyring_error {
+            Some(e) if reference.env.is_none() => Err(e),
+            _ => Ok(None),
+        }
+    }
+}
+
+/// Let `action` against `server`, the server of production profile
+/// `profile`, proceed if `confirmed` (`--yes-production`) or `prompt`
+/// answers yes
+pub fn confirm_production(
+    profile: &str,
+    server: &str,
+    action: &str,
+    confirmed: bool,
+    prompt: impl FnOnce(&str) -> io::Result<bool>,
+) -> ProfileResult<()> {
+    if confirmed {
+        return Ok(());
+    }
+    let question = format!(
+        "{} on production profile '{}' ({})? Type the profile name to confirm: ",
+        action, profile, server
+    );
+    match prompt(&question) {
+        Ok(true) => Ok(()),
+        _ => Err(ProfileError::ProductionUnconfirmed { profile: profile.to_string(), action: action.to_string() }),
+    }
+}
+
+/// [`confirm_production`] asking on the terminal, where the profile name
+/// must be typed back. Without a terminal only `confirmed` lets the action
+/// proceed.
+pub fn confirm_production_interactive(profile: &str, server: &str, action: &str, confirmed: bool) -> ProfileResult<()> {
+    confirm_production(profile, server, action, confirmed, |question| {
+        if !io::stdin().is_terminal() {
+            return Ok(false);
+        }
+        eprint!("{}", question);
+        io::stderr().flush()?;
+        let mut answer = String::new();
+        io::stdin().lock().read_line(&mut answer)?;
+        Ok(answer.trim() == profile)
+    })
+}
@@ -0,0 +1,239 @@
+//! Following the signal stream
+//!
+//! The stream is read as server-sent events from
+//! `/api/v1/signals/stream.sse`, which passes proxies that terminate
+//! WebSocket upgrades. Dropped connections are reopened with
+//! `Last-Event-ID`, so events are neither repeated nor lost as long as the
+//! server still holds them; events it no longer holds are reported as
+//! [`StreamItem::Lagged`].
+
+use std::time::Duration;
+
+use hal9_api_types::{SignalEvent, StreamFilter, StreamLagged, LAGGED_EVENT};
+use reqwest::header;
+use tracing::debug;
+
+use crate::client::Hal9Client;
+use crate::error::{ClientError, ClientResult};
+
+/// Path of the SSE signal stream
+const STREAM_PATH: &str = "/api/v1/signals/stream.sse";
+
+/// A connection silent for this long is reopened; the server sends a
+/// heartbeat every 15 seconds
+const IDLE_TIMEOUT: Duration = Duration::from_secs(45);
+
+/// What the signal stream yields
+#[derive(Debug, Clone, PartialEq)]
+pub enum StreamItem {
+    Event { id: u64, event: SignalEvent },
+    /// Events the server dropped before this client read them
+    Lagged(StreamLagged),
+}
+
+/// Open signal stream, see [`Hal9Client::stream_signals`]
+pub struct SignalStream {
+    client: Hal9Client,
+    filter: StreamFilter,
+    response: Option<reqwest::Response>,
+    parser: SseParser,
+    last_id: Option<u64>,
+    /// Failed connection attempts since events last arrived
+    failures: u32,
+}
+
+impl SignalStream {
+    pub(crate) fn new(client: Hal9Client, filter: StreamFilter) -> Self {
+        Self {
+            client,
+            filter,
+            response: None,
+            parser: SseParser::default(),
+            last_id: None,
+            failures: 0,
+        }
+    }
+
+    /// Id of the last event returned; a new stream resumes after it with
+    /// [`resume_after`](Self::resume_after)
+    pub fn last_id(&self) -> Option<u64> {
+        self.last_id
+    }
+
+    /// Continue after event `id` instead of with the next event
+    pub fn resume_after(mut self, id: u64) -> Self {
+        self.last_id = Some(id);
+        self
+    }
+
+    /// Next event, reconnecting as needed. Fails once reconnecting failed
+    /// more often than the client's `max_retries` in a row.
+    pub async fn next(&mut self) -> ClientResult<StreamItem> {
+        loop {
+            if let Some(frame) = self.parser.next_frame() {
+                if let Some(item) = self.item(frame)? {
+                    self.failures = 0;
+                    return Ok(item);
+                }
+                continue;
+            }
+
+            let error = match self.response.as_mut() {
+                Some(response) => match tokio::time::timeout(IDLE_TIMEOUT, response.chunk()).await {
+                    Ok(Ok(Some(chunk))) => {
+                        self.parser.feed(&chunk);
+                        continue;
+                    }
+                    Ok(Ok(None)) => None,
+                    Ok(Err(e)) => Some(ClientError::Transport(e)),
+                    Err(_) => Some(ClientError::Timeout("signal stream heartbeat".to_string())),
+                },
+                None => match self.connect().await {
+                    Ok(response) => {
+                        self.response = Some(response);
+                        continue;
+                    }
+                    Err(e) if !e.is_retryable() => return Err(e),
+                    Err(e) => Some(e),
+                },
+            };
+
+            // The connection ended; events in flight are resent after reconnecting
+            self.response = None;
+            self.parser = SseParser::default();
+            let config = self.client.config();
+            if self.failures >= config.max_retries {
+                return Err(error.unwrap_or_else(|| ClientError::Decode("signal stream closed".to_string())));
+            }
+            let delay = config.backoff_for(self.failures, None);
+            debug!(
+                "Signal stream disconnected ({}), reconnecting in {:?}",
+                error.map_or_else(|| "closed".to_string(), |e| e.to_string()),
+                delay,
+            );
+            self.failures += 1;
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    async fn connect(&self) -> ClientResult<reqwest::Response> {
+        let mut builder = self
+            .client
+            .stream_request(STREAM_PATH)
+            .header(header::ACCEPT, "text/event-stream")
+            .query(&self.filter.to_pairs());
+        if let Some(id) = self.last_id {
+            builder = builder.header("Last-Event-ID", id.to_string());
+        }
+        let response = self.client.authorize(builder).await?.send().await?;
+        if response.status().is_success() {
+            Ok(response)
+        } else {
+            Err(ClientError::from_response(response).await)
+        }
+    }
+
+    fn item(&mut self, frame: SseFrame) -> ClientResult<Option<StreamItem>> {
+        let Some(data) = frame.data else { return Ok(None) };
+        match frame.event.as_deref() {
+            Some(LAGGED_EVENT) => {
+                let lagged = serde_json::from_str(&data).map_err(|e| ClientError::Decode(e.to_string()))?;
+                Ok(Some(StreamItem::Lagged(lagged)))
+            }
+            Some(_) => Ok(None),
+            None => {
+                let event = serde_json::from_str(&data).map_err(|e| ClientError::Decode(e.to_string()))?;
+                let id = frame
+                    .id
+                    .and_then(|id| id.parse().ok())
+                    .ok_or_else(|| ClientError::Decode("signal stream event without id".to_string()))?;
+                self.last_id = Some(id);
+                Ok(Some(StreamItem::Event { id, event }))
+            }
+        }
+    }
+}
+
+/// One dispatched server-sent event
+#[derive(Debug, Default, PartialEq)]
+struct SseFrame {
+    event: Option<String>,
+    id: Option<String>,
+    data: Option<String>,
+}
+
+/// Splits a `text/event-stream` body into events. Comments, such as the
+/// server's heartbeats, are skipped.
+#[derive(Default)]
+struct SseParser {
+    buffer: Vec<u8>,
+    frame: SseFrame,
+    frames: std::collections::VecDeque<SseFrame>,
+}
+
+impl SseParser {
+    fn feed(&mut self, bytes: &[u8]) {
+        self.buffer.extend_from_slice(bytes);
+        while let Some(end) = self.buffer.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line);
+            self.line(line.trim_end_matches(['\n', '\r']));
+        }
+    }
+
+    fn line(&mut self, line: &str) {
+        if line.is_empty() {
+            let frame = std::mem::take(&mut self.frame);
+            if frame != SseFrame::default() {
+                self.frames.push_back(frame);
+            }
+            return;
+        }
+        if line.starts_with(':') {
+            return;
+        }
+        let (field, value) = line.split_once(':').unwrap_or((line, ""));
+        let value = value.strip_prefix(' ').unwrap_or(value).to_string();
+        match field {
+            "event" => self.frame.event = Some(value),
+            "id" => self.frame.id = Some(value),
+            "data" => match &mut self.frame.data {
+                Some(data) => {
+                    data.push('\n');
+                    data.push_str(&value);
+                }
+                None => self.frame.data = Some(value),
+            },
+            _ => {}
+        }
+    }
+
+    fn next_frame(&mut self) -> Option<SseFrame> {
+        self.frames.pop_front()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parser_splits_events_across_chunks() {
+        let mut parser = SseParser::default();
+        parser.feed(b": heartbeat\n\nid: 7\ndata: {\"a\":");
+        assert_eq!(parser.next_frame(), None);
+        parser.feed(b"1}\r\n\r\nevent: lagged\ndata: {\"skipped\":3}\n\n");
+
+        assert_eq!(parser.next_frame(), Some(SseFrame {
+            event: None,
+            id: Some("7".to_string()),
+            data: Some("{\"a\":1}".to_string()),
+        }));
+        assert_eq!(parser.next_frame(), Some(SseFrame {
+            event: Some("lagged".to_string()),
+            id: None,
+            data: Some("{\"skipped\":3}".to_string()),
+        }));
+        assert_eq!(parser.next_frame(), None);
+    }
+}
@@ -0,0 +1,356 @@
+//! Contract tests: every client method against the real server in mock mode
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use axum::{http::HeaderMap, http::StatusCode, routing::post, Json, Router};
+use hal9_client::{
+    ApiResponse, ChainStatus, ClientConfig, ClientError, Credentials, ErrorCode, Hal9Client,
+    ListQuery, SignalEvent, StreamFilter, StreamItem, SubmitSignalRequest, SubmitSignalResponse,
+    TokenPair, IDEMPOTENCY_KEY_HEADER,
+};
+use hal9_core::auth::{CreateUserRequest, UserRole};
+use hal9_core::sqlite::{SqlitePools, SqliteTuning};
+use hal9_core::ServerConfig;
+use hal9_server::{api, HAL9Server};
+
+struct TestServer {
+    url: String,
+    server: Arc<HAL9Server>,
+    _dir: tempfile::TempDir,
+}
+
+fn config(dir: &tempfile::TempDir, auth: bool) -> ServerConfig {
+    let yaml = format!(
+        r#"
+server_id: contract-test
+neurons:
+  - id: strategist
+    layer: L4
+    forward_connections: [coder]
+    backward_connections: []
+  - id: coder
+    layer: L2
+    forward_connections: []
+    backward_connections: [strategist]
+claude:
+  mode: mock
+  mock_responses:
+    L4:
+      - trigger: default
+        response: "FORWARD_TO: coder\nCONTENT: Build it"
+        delay_ms: 0
+    L2:
+      - trigger: default
+        response: "RESULT: done"
+        delay_ms: 0
+auth:
+  enabled: {auth}
+  jwt_secret: contract-test-secret
+  database_path: {dir}/auth.db
+layer_pause:
+  state_path: {dir}/layer_pauses.json
+"#,
+        auth = auth,
+        dir = dir.path().display(),
+    );
+    serde_yaml::from_str(&yaml).unwrap()
+}
+
+async fn start_server(auth: bool) -> TestServer {
+    let dir = tempfile::tempdir().unwrap();
+    let config = config(&dir, auth);
+    let mut server = HAL9Server::new(config.clone());
+    if auth {
+        let url = format!("sqlite:{}?mode=rwc", config.auth.database_path);
+        let pools = SqlitePools::connect(&url, SqliteTuning::default()).await.unwrap();
+        server.initialize_auth(pools).await.unwrap();
+        server.user_manager.as_ref().unwrap().create_user(CreateUserRequest {
+            username: "alice".to_string(),
+            email: "alice@example.com".to_string(),
+            password: "password123".to_string(),
+            role: Some(UserRole::User),
+        }).await.unwrap();
+    }
+    let server = Arc::new(server);
+    server.start().await.unwrap();
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let router = api::create_api_router(server.clone());
+    tokio::spawn(async move {
+        axum::serve(listener, router).await.unwrap();
+    });
+
+    TestServer { url, server, _dir: dir }
+}
+
+fn client(url: &str) -> Hal9Client {
+    Hal9Client::new(ClientConfig::new(url)).unwrap()
+}
+
+#[tokio::test]
+async fn test_health() {
+    let server = start_server(false).await;
+    let health = client(&server.url).health().await.unwrap();
+    assert_eq!(health.service, "hal9-server");
+    assert!(health.is_serving());
+    server.server.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_submit_signal_and_wait_for_chain() {
+    let server = start_server(false).await;
+    let client = client(&server.url);
+
+    let submitted = client.submit_signal(&SubmitSignalRequest::new("L4", "Plan the release")).await.unwrap();
+    assert_eq!(submitted.signal_id, submitted.chain_id);
+
+    let running = client.chain_result(&submitted.chain_id).await.unwrap();
+    assert_eq!(running.chain_id, submitted.chain_id);
+    assert_eq!(running.input, "Plan the release");
+
+    let finished = client.wait_for_chain(&submitted.chain_id, Duration::from_secs(10)).await.unwrap();
+    assert_ne!(finished.status, ChainStatus::Running);
+    server.server.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_stream_signals_follows_submissions() {
+    let server = start_server(false).await;
+    let client = client(&server.url);
+
+    let submitted = client.submit_signal(&SubmitSignalRequest::new("L4", "Plan the release")).await.unwrap();
+    let filter = StreamFilter { signal_id: Some(submitted.signal_id.clone()), ..Default::default() };
+    let mut stream = client.stream_signals(filter).resume_after(0);
+
+    match stream.next().await.unwrap() {
+        StreamItem::Event { id, event: SignalEvent::SignalUpdate { signal_id, status, .. } } => {
+            assert_eq!(signal_id, submitted.signal_id);
+            assert_eq!(status, "submitted");
+            assert_eq!(stream.last_id(), Some(id));
+        }
+        other => panic!("expected the submission, got {:?}", other),
+    }
+    server.server.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_invalid_layer_is_rejected() {
+    let server = start_server(false).await;
+    let err = client(&server.url)
+        .submit_signal(&SubmitSignalRequest::new("L42", "Nowhere"))
+        .await
+        .unwrap_err();
+    assert!(matches!(err, ClientError::Rejected(ref msg) if msg.contains("Invalid layer")), "{}", err);
+    server.server.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_submit_batch_reports_each_signal() {
+    let server = start_server(false).await;
+    let batch = client(&server.url)
+        .submit_batch(&[
+            SubmitSignalRequest::new("L4", "First"),
+            SubmitSignalRequest::new("L42", "Invalid"),
+            SubmitSignalRequest::new("L2", "Third"),
+        ])
+        .await
+        .unwrap();
+
+    assert_eq!((batch.accepted, batch.rejected), (2, 1));
+    assert_eq!(batch.results.iter().map(|r| r.index).collect::<Vec<_>>(), vec![0, 1, 2]);
+    assert!(batch.results[0].signal_id.is_some());
+    assert!(batch.results[1].error.as_deref().unwrap().contains("Invalid layer"));
+    assert!(batch.results[2].signal_id.is_some());
+    server.server.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_unknown_chain_is_not_found() {
+    let server = start_server(false).await;
+    let err = client(&server.url).chain_result("no-such-chain").await.unwrap_err();
+    assert_eq!(err.status(), Some(404));
+    assert_eq!(err.code(), Some(&ErrorCode::NotFound));
+    assert!(!err.is_retryable());
+    server.server.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_neurons_pages_and_filters() {
+    let server = start_server(false).await;
+    let client = client(&server.url);
+
+    let first = client.neurons(&ListQuery { limit: Some(1), ..Default::default() }).await.unwrap();
+    assert_eq!(first.items.len(), 1);
+    assert_eq!(first.total_estimate, 2);
+    let query = ListQuery { limit: Some(1), ..Default::default() };
+    let second = client.neurons(&query.next(&first).unwrap()).await.unwrap();
+    assert_ne!(second.items[0].id, first.items[0].id);
+
+    let filtered = client
+        .neurons(&ListQuery { filters: vec![("layer".to_string(), "L2".to_string())], ..Default::default() })
+        .await
+        .unwrap();
+    assert_eq!(filtered.items.len(), 1);
+
+    let neuron = client.neuron(&filtered.items[0].id).await.unwrap();
+    assert_eq!(neuron.layer, "L2");
+    server.server.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_costs_and_dead_letters() {
+    let server = start_server(false).await;
+    let client = client(&server.url);
+
+    let costs = client.costs().await.unwrap();
+    assert!(costs.total_cost >= 0.0);
+
+    let letters = client.dead_letters(&ListQuery::default()).await.unwrap();
+    assert!(letters.items.is_empty());
+    assert_eq!(letters.next_cursor, None);
+    server.server.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_password_credentials_log_in_on_first_use() {
+    let server = start_server(true).await;
+    let client = Hal9Client::new(ClientConfig::new(&server.url).credentials(Credentials::Password {
+        username: "alice".to_string(),
+        password: "password123".to_string(),
+    }))
+    .unwrap();
+
+    assert_eq!(client.profile().await.unwrap().username, "alice");
+    let refreshed = client.refresh().await.unwrap();
+    assert!(!refreshed.access_token.is_empty());
+    assert_eq!(client.profile().await.unwrap().username, "alice");
+    server.server.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_rejected_access_token_is_refreshed() {
+    let server = start_server(true).await;
+    let login = client(&server.url).login("alice", "password123").await.unwrap();
+    assert_eq!(login.user.username, "alice");
+
+    // A token the server rejects, paired with a valid refresh token
+    let client = Hal9Client::new(ClientConfig::new(&server.url).credentials(Credentials::Tokens(TokenPair {
+        access_token: "revoked".to_string(),
+        refresh_token: login.tokens.refresh_token,
+        expires_in: 900,
+    })))
+    .unwrap();
+    assert_eq!(client.profile().await.unwrap().username, "alice");
+    server.server.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_wrong_password_is_unauthorized() {
+    let server = start_server(true).await;
+    let err = client(&server.url).login("alice", "wrong").await.unwrap_err();
+    assert_eq!(err.code(), Some(&ErrorCode::Unauthorized));
+    server.server.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_idempotency_key_replays_first_submission() {
+    let server = start_server(false).await;
+    let http = reqwest::Client::new();
+    let submit = || {
+        http.post(format!("{}/api/v1/signal", server.url))
+            .header(IDEMPOTENCY_KEY_HEADER, "release-plan-1")
+            .json(&SubmitSignalRequest::new("L4", "Plan the release"))
+            .send()
+    };
+
+    let first: ApiResponse<SubmitSignalResponse> = submit().await.unwrap().json().await.unwrap();
+    let second: ApiResponse<SubmitSignalResponse> = submit().await.unwrap().json().await.unwrap();
+    assert_eq!(first.data.unwrap().signal_id, second.data.unwrap().signal_id);
+    server.server.shutdown().await.unwrap();
+}
+
+/// Answers 503 `failures` times, then accepts the signal; records the
+/// idempotency key of every attempt
+async fn start_flaky(failures: usize) -> (String, Arc<Mutex<Vec<String>>>) {
+    let attempts = Arc::new(AtomicUsize::new(0));
+    let keys = Arc::new(Mutex::new(Vec::new()));
+    let recorded = keys.clone();
+    let app = Router::new().route(
+        "/api/v1/signal",
+        post(move |headers: HeaderMap| {
+            let attempts = attempts.clone();
+            let keys = keys.clone();
+            async move {
+                let key = headers.get(IDEMPOTENCY_KEY_HEADER).unwrap().to_str().unwrap().to_string();
+                keys.lock().unwrap().push(key);
+                if attempts.fetch_add(1, Ordering::SeqCst) < failures {
+                    return (StatusCode::SERVICE_UNAVAILABLE, Json(serde_json::json!({
+                        "error": {"code": "SERVICE_UNAVAILABLE", "message": "draining"},
+                    })));
+                }
+                (StatusCode::OK, Json(serde_json::json!({
+                    "success": true,
+                    "data": {"signal_id": "s1", "chain_id": "s1", "message": "ok"},
+                    "error": null,
+                })))
+            }
+        }),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+    (url, recorded)
+}
+
+fn fast_retries(url: &str, max_retries: u32) -> ClientConfig {
+    ClientConfig::new(url)
+        .max_retries(max_retries)
+        .backoff(Duration::from_millis(10), Duration::from_millis(50))
+}
+
+#[tokio::test]
+async fn test_retryable_errors_are_retried_with_one_key() {
+    let (url, keys) = start_flaky(2).await;
+    let client = Hal9Client::new(fast_retries(&url, 3)).unwrap();
+
+    let submitted = client.submit_signal(&SubmitSignalRequest::new("L4", "Plan")).await.unwrap();
+    assert_eq!(submitted.signal_id, "s1");
+
+    let keys = keys.lock().unwrap();
+    assert_eq!(keys.len(), 3);
+    assert!(keys.iter().all(|k| *k == keys[0]));
+}
+
+#[tokio::test]
+async fn test_retries_give_up_after_max() {
+    let (url, keys) = start_flaky(10).await;
+    let client = Hal9Client::new(fast_retries(&url, 1)).unwrap();
+
+    let err = client.submit_signal(&SubmitSignalRequest::new("L4", "Plan")).await.unwrap_err();
+    assert_eq!(err.code(), Some(&ErrorCode::ServiceUnavailable));
+    assert_eq!(keys.lock().unwrap().len(), 2);
+}
+
+#[test]
+fn test_blocking_client() {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let server = runtime.block_on(start_server(false));
+
+    let url = server.url.clone();
+    std::thread::spawn(move || {
+        let client = hal9_client::blocking::Hal9Client::new(ClientConfig::new(url)).unwrap();
+        assert!(client.health().unwrap().is_serving());
+        let submitted = client.submit_signal(&SubmitSignalRequest::new("L4", "Plan")).unwrap();
+        assert_eq!(client.chain_result(&submitted.chain_id).unwrap().chain_id, submitted.chain_id);
+        assert_eq!(client.neurons(&ListQuery::default()).unwrap().items.len(), 2);
+    })
+    .join()
+    .unwrap();
+
+    runtime.block_on(server.server.shutdown()).unwrap();
+}
@@ -0,0 +1,163 @@
+//! Profiles shared by the command line tools: selection precedence, API key
+//! lookup and the production guard
+
+use std::collections::HashMap;
+use std::io;
+
+use hal9_client::profile::{
+    confirm_production, ApiKeyRef, Profile, ProfileError, ProfileFile, ProfileResult, SecretStore, PROFILE_ENV,
+};
+
+/// Keyring holding `entries`, or failing every lookup when `broken`
+struct FakeKeyring {
+    entries: HashMap<&'static str, &'static str>,
+    broken: bool,
+}
+
+impl FakeKeyring {
+    fn with(entries: &[(&'static str, &'static str)]) -> Self {
+        Self { entries: entries.iter().copied().collect(), broken: false }
+    }
+
+    fn broken() -> Self {
+        Self { entries: HashMap::new(), broken: true }
+    }
+}
+
+impl SecretStore for FakeKeyring {
+    fn get(&self, entry: &str) -> ProfileResult<Option<String>> {
+        if self.broken {
+            return Err(ProfileError::Keyring("no secret service".to_string()));
+        }
+        Ok(self.entries.get(entry).map(|secret| secret.to_string()))
+    }
+}
+
+fn env(vars: &'static [(&'static str, &'static str)]) -> impl Fn(&str) -> Option<String> {
+    move |name| vars.iter().find(|(key, _)| *key == name).map(|(_, value)| value.to_string())
+}
+
+fn profile(server: &str, api_key: Option<ApiKeyRef>) -> Profile {
+    Profile { server: server.to_string(), api_key, ..Default::default() }
+}
+
+fn file() -> ProfileFile {
+    let mut file = ProfileFile { default: Some("local".to_string()), ..Default::default() };
+    file.profiles.insert("local".to_string(), profile("localhost:8080", None));
+    file.profiles.insert("staging".to_string(), profile("https://staging.example.com", None));
+    file.profiles.insert(
+        "production".to_string(),
+        Profile { production: true, ..profile("https://hal9.example.com", None) },
+    );
+    file
+}
+
+fn resolve(file: &ProfileFile, flag: Option<&str>, vars: &'static [(&'static str, &'static str)]) -> Option<String> {
+    let resolved = file.resolve(flag, &env(vars), &FakeKeyring::with(&[])).unwrap();
+    resolved.map(|profile| profile.name)
+}
+
+#[test]
+fn test_profile_selection_precedence() {
+    let file = file();
+    let selected: &'static [(&str, &str)] = &[(PROFILE_ENV, "staging")];
+
+    // --profile, then HAL9_PROFILE, then the file's default
+    assert_eq!(resolve(&file, Some("production"), selected).as_deref(), Some("production"));
+    assert_eq!(resolve(&file, None, selected).as_deref(), Some("staging"));
+    assert_eq!(resolve(&file, None, &[]).as_deref(), Some("local"));
+    assert_eq!(resolve(&file, None, &[(PROFILE_ENV, "")]).as_deref(), Some("local"));
+
+    let resolved = file.resolve(None, &env(selected), &FakeKeyring::with(&[])).unwrap().unwrap();
+    assert_eq!(resolved.server, "https://staging.example.com");
+    assert!(!resolved.production);
+
+    // Without a default nothing is selected; a name the file lacks is an error
+    let no_default = ProfileFile { default: None, ..file.clone() };
+    assert_eq!(resolve(&no_default, None, &[]), None);
+    let err = file.resolve(Some("qa"), &env(&[]), &FakeKeyring::with(&[])).unwrap_err();
+    assert!(matches!(err, ProfileError::Unknown(ref name) if name == "qa"), "{}", err);
+}
+
+#[test]
+fn test_keyring_falls_back_to_env() {
+    let reference = ApiKeyRef {
+        keyring: Some("staging".to_string()),
+        env: Some("HAL9_STAGING_KEY".to_string()),
+        value: None,
+    };
+    let mut file = ProfileFile::default();
+    file.profiles.insert("staging".to_string(), profile("https://staging.example.com", Some(reference)));
+    let vars: &'static [(&str, &str)] = &[("HAL9_STAGING_KEY", "from-env")];
+    let key = |file: &ProfileFile, vars, keyring: &FakeKeyring| {
+        file.resolve(Some("staging"), &env(vars), keyring).map(|profile| profile.unwrap().api_key)
+    };
+
+    // The keyring wins when it holds the key
+    let keyring = FakeKeyring::with(&[("staging", "from-keyring")]);
+    assert_eq!(key(&file, vars, &keyring).unwrap().as_deref(), Some("from-keyring"));
+
+    // A missing entry or an unusable keyring falls back to the variable
+    assert_eq!(key(&file, vars, &FakeKeyring::with(&[])).unwrap().as_deref(), Some("from-env"));
+    assert_eq!(key(&file, vars, &FakeKeyring::broken()).unwrap().as_deref(), Some("from-env"));
+    assert_eq!(key(&file, &[], &FakeKeyring::with(&[])).unwrap(), None);
+
+    // With nothing to fall back to, the keyring's failure is reported
+    let keyring_only = ApiKeyRef { keyring: Some("staging".to_string()), ..Default::default() };
+    file.profiles.get_mut("staging").unwrap().api_key = Some(keyring_only);
+    let err = key(&file, vars, &FakeKeyring::broken()).unwrap_err();
+    assert!(matches!(err, ProfileError::Keyring(_)), "{}", err);
+
+    // Keys written into the file are refused unless explicitly allowed
+    let plaintext = ApiKeyRef { value: Some("in-file".to_string()), ..Default::default() };
+    file.profiles.get_mut("staging").unwrap().api_key = Some(plaintext);
+    let err = key(&file, &[], &FakeKeyring::with(&[])).unwrap_err();
+    assert!(err.to_string().contains("allow_plaintext_keys"), "{}", err);
+    file.allow_plaintext_keys = true;
+    assert_eq!(key(&file, &[], &FakeKeyring::with(&[])).unwrap().as_deref(), Some("in-file"));
+}
+
+#[test]
+fn test_production_guard() {
+    let file = file();
+    let never_asked = |_: &str| -> io::Result<bool> { panic!("prompted") };
+
+    // Only production servers are guarded, however their URL is written
+    assert_eq!(file.production_for("https://hal9.example.com/"), Some("production"));
+    assert_eq!(file.production_for("https://staging.example.com"), None);
+
+    // --yes-production skips the prompt
+    confirm_production("production", "https://hal9.example.com", "stop the server", true, never_asked).unwrap();
+
+    // Otherwise the prompt decides, naming the profile and its server
+    confirm_production("production", "https://hal9.example.com", "stop the server", false, |question| {
+        assert!(question.contains("'production'") && question.contains("https://hal9.example.com"), "{}", question);
+        Ok(true)
+    })
+    .unwrap();
+    let err = confirm_production("production", "https://hal9.example.com", "stop the server", false, |_| Ok(false))
+        .unwrap_err();
+    assert!(matches!(err, ProfileError::ProductionUnconfirmed { .. }), "{}", err);
+    assert!(err.to_string().contains("--yes-production"), "{}", err);
+
+    // A prompt that cannot be answered refuses as well
+    let closed = |_: &str| Err(io::Error::new(io::ErrorKind::UnexpectedEof, "closed"));
+    let err = confirm_production("production", "https://hal9.example.com", "roll back", false, closed).unwrap_err();
+    assert!(err.to_string().contains("roll back"), "{}", err);
+}
+
+#[test]
+fn test_profile_file_round_trip() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("hal9").join("config.toml");
+    assert_eq!(ProfileFile::load(&path).unwrap(), ProfileFile::default());
+
+    let mut file = file();
+    file.profiles.get_mut("staging").unwrap().api_key =
+        Some(ApiKeyRef { env: Some("HAL9_STAGING_KEY".to_string()), ..Default::default() });
+    file.save(&path).unwrap();
+    assert_eq!(ProfileFile::load(&path).unwrap(), file);
+
+    std::fs::write(&path, "profiles = 3").unwrap();
+    assert!(matches!(ProfileFile::load(&path), Err(ProfileError::Parse { .. })));
+}
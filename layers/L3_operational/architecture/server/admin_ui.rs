@@ -0,0 +1,219 @@
+//! Embedded admin UI, built with the `admin-ui` feature
+//!
+//! `GET /admin` serves a single page, compiled into the binary together
+//! with its script and stylesheet, that drives the JSON API: neurons with
+//! their health and queue depth, the live signal stream, spend per layer,
+//! the dead letter queue and layer pauses. The operator enters a token once;
+//! the page keeps it in `sessionStorage` and sends it with every call.
+//!
+//! The page itself holds no data and is served to anyone. What a token may
+//! do is asked of `GET /admin/session`, which needs a valid token when auth
+//! is enabled, and the page hides the panels and controls it may not use.
+//!
+//! Assets are linked with a content hash in their URL and cached for a
+//! year under it; the page is revalidated on every load by its ETag, so a
+//! new build is picked up at once.
+
+use std::sync::OnceLock;
+
+use axum::{
+    extract::{Path, Query},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    middleware,
+    response::{IntoResponse, Response},
+    routing::get,
+    Extension, Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use hal9_api_types::ApiResponse;
+use hal9_core::auth::{Permission, Permissions};
+
+use crate::{
+    auth_middleware::{auth_middleware, AuthState, AuthUser},
+    error::ServerError,
+};
+
+/// `Cache-Control` of versioned asset URLs
+pub const IMMUTABLE: &str = "public, max-age=31536000, immutable";
+
+/// `Cache-Control` of the page and of assets asked for without their version
+pub const REVALIDATE: &str = "no-cache";
+
+const INDEX: &str = include_str!("admin_ui/index.html");
+
+/// Script and stylesheet of the page: name, content type and content
+const ASSETS: &[(&str, &str, &str)] = &[
+    ("admin.js", "text/javascript; charset=utf-8", include_str!("admin_ui/admin.js")),
+    ("admin.css", "text/css; charset=utf-8", include_str!("admin_ui/admin.css")),
+];
+
+/// What the page may show or do, and the permission each needs
+const CAPABILITIES: &[(&str, Permission)] = &[
+    ("neurons", Permission::ViewNeuron),
+    ("signals", Permission::ViewSignals),
+    ("dead_letters", Permission::ViewSignals),
+    ("retry_dead_letters", Permission::SystemAdmin),
+    ("costs", Permission::ViewCosts),
+    ("layers", Permission::SystemAdmin),
+    ("pause_layers", Permission::SystemAdmin),
+];
+
+/// An embedded file with its content hash
+struct Asset {
+    content_type: &'static str,
+    body: String,
+    version: String,
+}
+
+impl Asset {
+    fn new(content_type: &'static str, body: String) -> Self {
+        let version = Sha256::digest(body.as_bytes())[..8].iter().map(|b| format!("{:02x}", b)).collect();
+        Self { content_type, body, version }
+    }
+
+    fn etag(&self) -> String {
+        format!("\"{}\"", self.version)
+    }
+}
+
+/// The page, with links to the current versions of its assets
+fn index() -> &'static Asset {
+    static INDEX_ASSET: OnceLock<Asset> = OnceLock::new();
+    INDEX_ASSET.get_or_init(|| {
+        let body = ASSETS.iter().fold(INDEX.to_string(), |page, (name, _, _)| {
+            let asset = asset(name).expect("embedded asset");
+            page.replace(&format!("{{{{{}}}}}", name), &asset_url(name, &asset.version))
+        });
+        Asset::new("text/html; charset=utf-8", body)
+    })
+}
+
+fn asset(name: &str) -> Option<&'static Asset> {
+    static ASSET_TABLE: OnceLock<Vec<(&'static str, Asset)>> = OnceLock::new();
+    ASSET_TABLE
+        .get_or_init(|| {
+            ASSETS
+                .iter()
+                .map(|(name, content_type, body)| (*name, Asset::new(content_type, body.to_string())))
+                .collect()
+        })
+        .iter()
+        .find(|(asset_name, _)| *asset_name == name)
+        .map(|(_, asset)| asset)
+}
+
+/// Versioned URL of an embedded asset, as linked from the page
+pub fn asset_url(name: &str, version: &str) -> String {
+    format!("/admin/assets/{}?v={}", name, version)
+}
+
+/// The embedded page, script and stylesheet, for inspection
+pub fn embedded(name: &str) -> Option<&'static str> {
+    match name {
+        "index.html" => Some(INDEX),
+        _ => ASSETS.iter().find(|(asset_name, _, _)| *asset_name == name).map(|(_, _, body)| *body),
+    }
+}
+
+/// Response for `asset`, or 304 if the client holds the same version
+fn serve(asset: &Asset, headers: &HeaderMap, cache_control: &'static str) -> Response {
+    let etag = asset.etag();
+    let cached = headers
+        .get(header::IF_NONE_MATCH)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|tags| tags.split(',').any(|tag| tag.trim() == etag || tag.trim() == "*"));
+    let mut response = if cached {
+        StatusCode::NOT_MODIFIED.into_response()
+    } else {
+        ([(header::CONTENT_TYPE, asset.content_type)], asset.body.clone()).into_response()
+    };
+
+    let headers = response.headers_mut();
+    headers.insert(header::CACHE_CONTROL, HeaderValue::from_static(cache_control));
+    if let Ok(etag) = HeaderValue::from_str(&etag) {
+        headers.insert(header::ETAG, etag);
+    }
+    headers.insert(header::X_CONTENT_TYPE_OPTIONS, HeaderValue::from_static("nosniff"));
+    response
+}
+
+async fn get_index(headers: HeaderMap) -> Response {
+    let mut response = serve(index(), &headers, REVALIDATE);
+    response.headers_mut().insert(header::X_FRAME_OPTIONS, HeaderValue::from_static("DENY"));
+    response
+}
+
+#[derive(Debug, Deserialize)]
+struct AssetQuery {
+    v: Option<String>,
+}
+
+async fn get_asset(
+    Path(name): Path<String>,
+    Query(query): Query<AssetQuery>,
+    headers: HeaderMap,
+) -> Result<Response, ServerError> {
+    let asset = asset(&name).ok_or_else(|| ServerError::NotFound(format!("Admin UI asset {} not found", name)))?;
+    // Only the URL naming this content may be cached for good
+    let cache_control = if query.v.as_deref() == Some(asset.version.as_str()) {
+        IMMUTABLE
+    } else {
+        REVALIDATE
+    };
+    Ok(serve(asset, &headers, cache_control))
+}
+
+/// Who the page is used by and what it may show or do
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AdminSession {
+    /// Whether the server checks tokens; without auth everything is allowed
+    pub auth: bool,
+    pub username: Option<String>,
+    pub role: Option<String>,
+    pub capabilities: Vec<String>,
+}
+
+/// Capabilities granted by `permissions`, all of them without auth
+pub fn capabilities(permissions: Option<&Permissions>) -> Vec<String> {
+    CAPABILITIES
+        .iter()
+        .filter(|(_, permission)| permissions.is_none_or(|granted| granted.has(permission)))
+        .map(|(name, _)| name.to_string())
+        .collect()
+}
+
+async fn get_session(user: Option<Extension<AuthUser>>) -> Result<Response, ServerError> {
+    let session = match user {
+        Some(Extension(user)) => AdminSession {
+            auth: true,
+            capabilities: capabilities(Some(&user.permissions)),
+            username: Some(user.username),
+            role: Some(user.role),
+        },
+        None => AdminSession { auth: false, username: None, role: None, capabilities: capabilities(None) },
+    };
+    if session.capabilities.is_empty() {
+        return Err(ServerError::Forbidden("Token grants nothing the admin UI can show".to_string()));
+    }
+    let mut response = Json(ApiResponse::success(session)).into_response();
+    response.headers_mut().insert(header::CACHE_CONTROL, HeaderValue::from_static("no-store"));
+    Ok(response)
+}
+
+/// Routes of the admin UI. With `auth_state` the session needs a valid token.
+pub fn router<S>(auth_state: Option<AuthState>) -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    let mut session = Router::new().route("/admin/session", get(get_session));
+    if let Some(auth_state) = auth_state {
+        session = session.route_layer(middleware::from_fn_with_state(auth_state, auth_middleware));
+    }
+
+    Router::new()
+        .route("/admin", get(get_index))
+        .route("/admin/assets/:name", get(get_asset))
+        .merge(session)
+}
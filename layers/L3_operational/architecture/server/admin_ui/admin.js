@@ -0,0 +1,349 @@
+// HAL9 admin UI. Plain script, no build step: every call goes through
+// ENDPOINTS, so the server's tests can check each of them exists.
+"use strict";
+
+const ENDPOINTS = {
+  session: ["GET", "/admin/session"],
+  neurons: ["GET", "/api/v1/neurons"],
+  layers: ["GET", "/api/v1/admin/layers"],
+  pauseLayer: ["POST", "/api/v1/admin/layers/:layer/pause"],
+  resumeLayer: ["POST", "/api/v1/admin/layers/:layer/resume"],
+  costs: ["GET", "/api/v1/costs"],
+  deadLetters: ["GET", "/api/v1/dead-letters"],
+  retryDeadLetter: ["POST", "/api/v1/dead-letters/:id/retry"],
+  signalStream: ["GET", "/api/v1/signals/stream.sse"],
+};
+
+const TOKEN_KEY = "hal9.admin.token";
+const REFRESH_MS = 5000;
+const STREAM_LINES = 200;
+
+let session = null;
+let refreshTimer = null;
+let stream = null;
+let deadLetterCursor = null;
+
+const $ = (id) => document.getElementById(id);
+
+function el(tag, attrs, ...children) {
+  const node = document.createElement(tag);
+  Object.entries(attrs || {}).forEach(([key, value]) => {
+    if (key === "onclick") {
+      node.addEventListener("click", value);
+    } else {
+      node.setAttribute(key, value);
+    }
+  });
+  children.forEach((child) => node.append(child instanceof Node ? child : String(child)));
+  return node;
+}
+
+function can(capability) {
+  return session !== null && session.capabilities.includes(capability);
+}
+
+// Access tokens are JWTs; anything else is sent as an API key
+function authHeaders() {
+  const token = sessionStorage.getItem(TOKEN_KEY);
+  if (!token) {
+    return {};
+  }
+  return token.split(".").length === 3
+    ? { Authorization: "Bearer " + token }
+    : { "X-API-Key": token };
+}
+
+function url(name, params, query) {
+  let path = ENDPOINTS[name][1];
+  Object.entries(params || {}).forEach(([key, value]) => {
+    path = path.replace(":" + key, encodeURIComponent(value));
+  });
+  const search = new URLSearchParams(query || {}).toString();
+  return search ? path + "?" + search : path;
+}
+
+async function call(name, params, query) {
+  const response = await fetch(url(name, params, query), {
+    method: ENDPOINTS[name][0],
+    headers: authHeaders(),
+  });
+  if (response.status === 401) {
+    signOut(sessionStorage.getItem(TOKEN_KEY) ? "Token rejected or expired" : "");
+    throw new Error("unauthorized");
+  }
+  const body = await response.json().catch(() => null);
+  if (!response.ok || !body || body.success === false) {
+    throw new Error((body && body.error) || response.status + " " + response.statusText);
+  }
+  return body.data;
+}
+
+// Sign in
+
+async function signIn() {
+  try {
+    session = await call("session");
+  } catch (error) {
+    if (error.message !== "unauthorized") {
+      showSignIn(error.message);
+    }
+    return;
+  }
+
+  $("sign-in").hidden = true;
+  $("panels").hidden = false;
+  $("sign-out").hidden = !session.auth;
+  $("whoami").textContent = session.auth ? session.username + " (" + session.role + ")" : "auth disabled";
+  document.querySelectorAll("[data-capability]").forEach((panel) => {
+    panel.hidden = !can(panel.dataset.capability);
+  });
+
+  refresh();
+  refreshTimer = setInterval(refresh, REFRESH_MS);
+  if (can("signals")) {
+    startStream();
+  }
+}
+
+function showSignIn(message) {
+  $("panels").hidden = true;
+  $("sign-out").hidden = true;
+  $("whoami").textContent = "";
+  $("sign-in").hidden = false;
+  $("sign-in-error").textContent = message || "";
+}
+
+function signOut(message) {
+  sessionStorage.removeItem(TOKEN_KEY);
+  session = null;
+  clearInterval(refreshTimer);
+  if (stream) {
+    stream.abort();
+    stream = null;
+  }
+  showSignIn(message);
+}
+
+// Panels
+
+function refresh() {
+  if (can("neurons")) {
+    loadNeurons().catch(showError("neurons"));
+  }
+  if (can("layers")) {
+    loadLayers().catch(showError("layers"));
+  }
+  if (can("costs")) {
+    loadCosts().catch(showError("cost-summary"));
+  }
+  if (can("dead_letters") && deadLetterCursor === null) {
+    loadDeadLetters(false).catch(showError("dead-letters"));
+  }
+}
+
+function showError(id) {
+  return (error) => {
+    if (error.message === "unauthorized") {
+      return;
+    }
+    const target = $(id);
+    target.replaceChildren(target.tagName === "TBODY"
+      ? el("tr", { class: "error" }, el("td", { colspan: "6" }, error.message))
+      : el("span", { class: "error" }, error.message));
+  };
+}
+
+async function loadNeurons() {
+  const page = await call("neurons", null, { limit: "500", sort: "layer" });
+  $("neurons").replaceChildren(...page.items.map((neuron) => el("tr", {},
+    el("td", {}, neuron.id),
+    el("td", {}, neuron.layer),
+    el("td", {}, neuron.state),
+    el("td", { class: neuron.is_healthy ? "healthy" : "unhealthy" }, neuron.is_healthy ? "healthy" : "unhealthy"),
+    el("td", {}, neuron.concurrency.in_flight),
+    el("td", {}, neuron.concurrency.queued),
+  )));
+}
+
+async function loadLayers() {
+  const [paused, neurons] = await Promise.all([
+    call("layers"),
+    call("neurons", null, { limit: "500" }),
+  ]);
+  const byLayer = new Map(paused.map((status) => [status.layer, status]));
+  const layers = [...new Set(neurons.items.map((neuron) => neuron.layer).concat([...byLayer.keys()]))].sort();
+  $("layers").replaceChildren(...layers.map((layer) => {
+    const status = byLayer.get(layer);
+    const row = el("tr", {},
+      el("td", {}, layer),
+      el("td", {}, status ? "paused" + (status.reason ? ": " + status.reason : "") : "running"),
+      el("td", {}, status ? status.held : 0),
+    );
+    const action = el("td");
+    if (can("pause_layers")) {
+      const name = status ? "resumeLayer" : "pauseLayer";
+      action.append(el("button", {
+        type: "button",
+        onclick: () => call(name, { layer }).then(loadLayers).catch((error) => alert(error.message)),
+      }, status ? "Resume" : "Pause"));
+    }
+    row.append(action);
+    return row;
+  }));
+}
+
+async function loadCosts() {
+  const costs = await call("costs");
+  $("cost-summary").textContent =
+    "Hour $" + costs.hourly_cost.toFixed(2) + " of $" + costs.hourly_limit.toFixed(2) +
+    ", day $" + costs.daily_cost.toFixed(2) + " of $" + costs.daily_limit.toFixed(2) +
+    ", total $" + costs.total_cost.toFixed(2);
+  const layers = costs.by_layer || [];
+  const highest = Math.max(...layers.map((layer) => layer.cost), 0);
+  $("cost-layers").replaceChildren(...layers.map((layer) => {
+    const width = highest > 0 ? (100 * layer.cost) / highest : 0;
+    return el("div", { class: "bar" },
+      el("span", {}, layer.layer),
+      el("div", {}, el("div", { class: "fill", style: "width: " + width.toFixed(1) + "%" })),
+      el("span", {}, "$" + layer.cost.toFixed(4) + " / " + layer.tokens + " tok"),
+    );
+  }));
+}
+
+async function loadDeadLetters(more) {
+  const query = { limit: "50" };
+  if (more && deadLetterCursor) {
+    query.cursor = deadLetterCursor;
+  }
+  const page = await call("deadLetters", null, query);
+  const rows = page.items.map((letter) => {
+    const id = letter.signal.signal_id;
+    const action = el("td");
+    if (can("retry_dead_letters") && letter.signal.propagation_type === "Forward") {
+      action.append(el("button", {
+        type: "button",
+        onclick: () => call("retryDeadLetter", { id })
+          .then(() => loadDeadLetters(false))
+          .catch((error) => alert(error.message)),
+      }, "Retry"));
+    }
+    return el("tr", {},
+      el("td", {}, new Date(letter.timestamp).toLocaleString()),
+      el("td", {}, letter.neuron_id),
+      el("td", {}, letter.reason),
+      el("td", { class: "content", title: letter.signal.payload.activation.content },
+        letter.signal.payload.activation.content),
+      action,
+    );
+  });
+  if (more) {
+    $("dead-letters").append(...rows);
+  } else {
+    $("dead-letters").replaceChildren(...rows);
+  }
+  deadLetterCursor = page.next_cursor || null;
+  $("dead-letters-more").hidden = deadLetterCursor === null;
+}
+
+// Signal stream: EventSource cannot send a token, so the event stream is
+// read with fetch and resumed from the last event id when it drops
+
+async function startStream() {
+  let lastEventId = null;
+  stream = new AbortController();
+  const signal = stream.signal;
+  while (!signal.aborted) {
+    try {
+      const headers = authHeaders();
+      if (lastEventId !== null) {
+        headers["Last-Event-ID"] = lastEventId;
+      }
+      const response = await fetch(url("signalStream"), { headers, signal });
+      if (!response.ok) {
+        throw new Error(response.status + " " + response.statusText);
+      }
+      $("stream-status").textContent = "live";
+      const reader = response.body.pipeThrough(new TextDecoderStream()).getReader();
+      let buffer = "";
+      for (;;) {
+        const { value, done } = await reader.read();
+        if (done) {
+          break;
+        }
+        buffer += value;
+        let end;
+        while ((end = buffer.indexOf("\n\n")) >= 0) {
+          const event = parseEvent(buffer.slice(0, end));
+          buffer = buffer.slice(end + 2);
+          if (event.id !== null) {
+            lastEventId = event.id;
+          }
+          if (event.data !== null) {
+            showEvent(event);
+          }
+        }
+      }
+    } catch (error) {
+      if (signal.aborted) {
+        return;
+      }
+      $("stream-status").textContent = "reconnecting (" + error.message + ")";
+    }
+    await new Promise((resolve) => setTimeout(resolve, 2000));
+  }
+}
+
+function parseEvent(text) {
+  const event = { id: null, type: "message", data: null };
+  text.split("\n").forEach((line) => {
+    const colon = line.indexOf(":");
+    if (colon <= 0) {
+      return;
+    }
+    const field = line.slice(0, colon);
+    const value = line.slice(colon + 1).replace(/^ /, "");
+    if (field === "id") {
+      event.id = value;
+    } else if (field === "event") {
+      event.type = value;
+    } else if (field === "data") {
+      event.data = event.data === null ? value : event.data + "\n" + value;
+    }
+  });
+  return event;
+}
+
+function showEvent(event) {
+  let text = event.data;
+  try {
+    const data = JSON.parse(event.data);
+    if (data.type === "signal_update") {
+      text = data.signal_id + " " + data.neuron_id + " " + data.status;
+    } else if (data.type === "neuron_state_change") {
+      text = data.neuron_id + " " + data.old_state + " -> " + data.new_state;
+    } else if (data.type === "server_event") {
+      text = data.event + ": " + data.details;
+    }
+  } catch (_) {
+    // Shown as sent
+  }
+  const list = $("signals");
+  list.prepend(el("li", {}, new Date().toLocaleTimeString() + " " + text));
+  while (list.children.length > STREAM_LINES) {
+    list.lastChild.remove();
+  }
+}
+
+document.addEventListener("DOMContentLoaded", () => {
+  $("sign-in").addEventListener("submit", (event) => {
+    event.preventDefault();
+    sessionStorage.setItem(TOKEN_KEY, $("token").value.trim());
+    $("token").value = "";
+    signIn();
+  });
+  $("sign-out").addEventListener("click", () => signOut());
+  $("dead-letters-more").addEventListener("click", () => {
+    loadDeadLetters(true).catch(showError("dead-letters"));
+  });
+  signIn();
+});
@@ -1,11 +1,12 @@
 //! HTTP API endpoints for HAL9 server
 
 use axum::{
-    extract::{State, Json, Path, Query},
+    body::Body,
+    extract::{DefaultBodyLimit, State, Json, Path, Query, Extension},
     response::{IntoResponse, Response},
     routing::{get, post, put, delete},
     Router,
-    http::StatusCode,
+    http::{HeaderMap, StatusCode},
     middleware,
 };
 use serde::{Deserialize, Serialize};
@@ -16,51 +17,85 @@ use tower_http::cors::CorsLayer;
 use crate::{
     server::HAL9Server, 
     error::ServerError,
-    auth_middleware::{auth_middleware as auth_mw, AuthState},
+    approvals::PendingApproval,
+    auth_middleware::{auth_middleware as auth_mw, optional_auth_middleware as optional_auth_mw, require_permission, AuthState, AuthUser},
+    chain_limits::ChainOwner,
+    chain_tracker::SignalActivity,
+    chain_visualization::VisualizationFormat,
+    chaos::ChaosRequest,
+    cost_tags::{self, TAGS_KEY},
+    concurrency::DeadLetter,
     api_auth,
     api_codegen,
+    api_mcp,
+    api_orgs,
+    api_webhooks,
     middleware::logging_middleware,
-    rate_limiter::{RateLimiter, RateLimitConfig},
-    health::{health_check_simple, health_check_detailed, liveness_probe, readiness_probe},
+    org_usage::parse_period,
+    output_format::request_format,
+    rate_limiter::{LimitMode, RateLimiter, RateLimitConfig, RedisBackend},
+    health::{health_check_simple, health_check_detail, health_check_detailed, liveness_probe, readiness_probe},
     error_recovery::{error_recovery_middleware, ErrorStore},
+    event_stream,
+    idempotency::IdempotencyCache,
+    ingestion::{self, quarantine::QuarantinedMessage},
+    quality::{NewBenchmark, QualityRun},
+    templates::NewTemplate,
+    log_index::LogQuery,
+    memory_manager::{DumpScope, ImportOptions},
+    pagination::{paginate, ListParams},
+    read_only::read_only_middleware,
+    shadow::shadow_middleware,
+    report_privacy,
+    self_organizer::ScalingEvent,
+    server::NeuronInfo,
+    sessions::SESSION_ID_KEY,
+    error_recovery::ErrorContext,
+    logging,
 };
-use hal9_core::NeuronSignal;
+use hal9_api_types::{
+    ApiResponse, BatchItemResult, BatchSubmitRequest, BatchSubmitResponse, StreamFilter,
+    SubmitSignalRequest, SubmitSignalResponse, SubmitTemplateRequest, SubmitTemplateResponse,
+};
+use hal9_core::{auth::Permission, memory::MemoryEntry, NeuronSignal, PropagationType};
+use hal9_core::hierarchical::intelligence::{Challenge, Constraint, Criterion, DecompositionStrategy, Goal};
 
 #[cfg(feature = "graphql")]
 pub mod graphql;
 
-/// API response wrapper
-#[derive(Debug, Serialize)]
-struct ApiResponse<T> {
-    success: bool,
-    data: Option<T>,
-    error: Option<String>,
-}
-
-impl<T: Serialize> ApiResponse<T> {
-    fn success(data: T) -> Self {
-        Self {
-            success: true,
-            data: Some(data),
-            error: None,
-        }
-    }
+/// Most signals accepted by one batch submission
+pub const MAX_BATCH_SIZE: usize = 100;
 
-    fn error(msg: impl Into<String>) -> Self {
-        Self {
-            success: false,
-            data: None,
-            error: Some(msg.into()),
-        }
-    }
+/// Creative challenge request
+#[derive(Debug, Deserialize)]
+struct CreateSolutionsRequest {
+    problem_statement: String,
+    #[serde(default)]
+    context: HashMap<String, serde_json::Value>,
+    #[serde(default)]
+    constraints: Vec<Constraint>,
 }
 
-/// Signal submission request
+/// Goal creation request
 #[derive(Debug, Deserialize)]
-struct SubmitSignalRequest {
-    content: String,
-    layer: String,
-    neuron_id: Option<String>,
+struct CreateGoalRequest {
+    description: String,
+    #[serde(default = "default_goal_priority")]
+    priority: f32,
+    #[serde(default)]
+    constraints: Vec<Constraint>,
+    #[serde(default)]
+    success_criteria: Vec<Criterion>,
+    #[serde(default = "default_decomposition_strategy")]
+    decomposition_strategy: DecompositionStrategy,
+}
+
+fn default_goal_priority() -> f32 {
+    0.5
+}
+
+fn default_decomposition_strategy() -> DecompositionStrategy {
+    DecompositionStrategy::Hierarchical
 }
 
 /// Server status response
@@ -71,6 +106,9 @@ struct ServerStatus {
     neurons: Vec<NeuronStatus>,
     metrics: MetricsSummary,
     network_status: Option<crate::server::NetworkStatus>,
+    paused_layers: Vec<crate::layer_pause::PausedLayerStatus>,
+    degraded: crate::degraded::DegradedStatus,
+    read_only: crate::read_only::ReadOnlyStatus,
 }
 
 /// Individual neuron status
@@ -107,25 +145,8 @@ struct TraceStep {
     response: Option<String>,
 }
 
-/// WebSocket message types
-#[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(tag = "type", rename_all = "snake_case")]
-pub enum WsMessage {
-    SignalUpdate {
-        signal_id: String,
-        neuron_id: String,
-        status: String,
-    },
-    NeuronStateChange {
-        neuron_id: String,
-        old_state: String,
-        new_state: String,
-    },
-    ServerEvent {
-        event: String,
-        details: String,
-    },
-}
+/// Events of the signal stream, sent over WebSocket and SSE
+pub use hal9_api_types::SignalEvent as WsMessage;
 
 /// Create the HTTP API router
 pub fn create_api_router(server: Arc<HAL9Server>) -> Router {
@@ -148,16 +169,202 @@ pub fn create_api_router(server: Arc<HAL9Server>) -> Router {
             .ok()
             .and_then(|s| s.parse().ok())
             .unwrap_or(10),
+        ip_mode: rate_limit_mode("RATE_LIMIT_IP_MODE"),
+        user_mode: rate_limit_mode("RATE_LIMIT_USER_MODE"),
+        max_unsynced: std::env::var("RATE_LIMIT_MAX_UNSYNCED")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(5),
+        sync_interval: std::time::Duration::from_millis(
+            std::env::var("RATE_LIMIT_SYNC_INTERVAL_MS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(250)
+        ),
     };
     
-    let rate_limiter = Arc::new(RateLimiter::new(rate_limiter_config));
+    // Limits shared across instances are coordinated through Redis
+    let redis_url = std::env::var("RATE_LIMIT_REDIS_URL").or_else(|_| std::env::var("REDIS_URL"));
+    let rate_limiter = match (rate_limiter_config.is_distributed(), redis_url) {
+        (true, Ok(url)) => match RedisBackend::new(&url) {
+            Ok(backend) => RateLimiter::with_backend(rate_limiter_config, Arc::new(backend)),
+            Err(e) => {
+                tracing::warn!("{}; rate limits stay per instance", e);
+                RateLimiter::new(rate_limiter_config)
+            }
+        },
+        (true, Err(_)) => {
+            tracing::warn!("Distributed rate limits need RATE_LIMIT_REDIS_URL or REDIS_URL; rate limits stay per instance");
+            RateLimiter::new(rate_limiter_config)
+        }
+        (false, _) => RateLimiter::new(rate_limiter_config),
+    };
+    rate_limiter.set_metrics(server.metrics());
+    let rate_limiter = Arc::new(rate_limiter);
     
     // Create error store for debugging
     let error_store = Arc::new(ErrorStore::new(1000));
     
+    // Responses replayed to clients retrying with the same idempotency key
+    let idempotency = Arc::new(IdempotencyCache::default());
+    
+    // Authentication state, when auth is enabled
+    let auth_state = server.jwt_manager.clone()
+        .zip(server.api_key_manager.clone())
+        .zip(server.user_manager.clone())
+        .map(|((jwt_manager, api_key_manager), user_manager)| AuthState { jwt_manager, api_key_manager, user_manager });
+    
+    // Admin endpoints; system admins only when auth is enabled
+    let mut admin_router = Router::new()
+        // Recent logs and runtime log levels
+        .route("/api/v1/logs", get(query_logs))
+        .route("/api/v1/logs/stats", get(get_log_stats))
+        .route("/api/v1/admin/log-levels", get(get_log_levels).put(set_log_level))
+        .route("/api/v1/admin/log-levels/:module", delete(clear_log_level))
+        
+        // Layer pauses
+        .route("/api/v1/admin/layers", get(get_paused_layers))
+        .route("/api/v1/admin/layers/:layer/pause", post(pause_layer))
+        .route("/api/v1/admin/layers/:layer/resume", post(resume_layer))
+        
+        // Draining before termination, e.g. from a preStop hook
+        .route("/api/v1/admin/drain", post(begin_drain))
+        
+        // Effective config and runtime overrides
+        .route("/api/v1/admin/config/effective", get(get_effective_config))
+        .route("/api/v1/admin/config/overrides", put(set_config_override))
+        .route("/api/v1/admin/config/overrides/:path", delete(clear_config_override))
+        
+        // Warm pools of slow-starting neurons
+        .route("/api/v1/admin/warm-pools", get(get_warm_pools))
+        .route("/api/v1/admin/warm-pools/:neuron_type", put(set_warm_pool_size))
+        
+        // Topology patches
+        .route("/api/v1/admin/topology", get(get_topology))
+        .route("/api/v1/admin/topology/patch", post(patch_topology))
+        .route("/api/v1/admin/topology/revert", post(revert_topology))
+        
+        // Read-only mode for maintenance windows
+        .route("/api/v1/admin/readonly", get(get_read_only).post(set_read_only))
+        
+        // Retention and database compaction
+        .route("/api/v1/admin/retention", get(get_retention))
+        .route("/api/v1/admin/retention/run", post(run_retention))
+        .route("/api/v1/admin/compact", post(compact_databases))
+        
+        // Provider call timeouts and the adaptive kill switch
+        .route("/api/v1/admin/timeouts", get(get_timeouts))
+        .route("/api/v1/admin/timeouts/adaptive", put(set_adaptive_timeouts))
+        
+        // Chaos injection in debug and `chaos` feature builds
+        .route("/api/v1/admin/chaos", get(get_chaos).post(inject_chaos).delete(clear_chaos))
+        .route("/api/v1/admin/chaos/:id", delete(stop_chaos))
+        
+        // Mock scenario replay and recording
+        .route("/api/v1/admin/mock/scenarios", get(get_mock_scenarios))
+        .route("/api/v1/admin/mock/scenario", put(select_mock_scenario))
+        .route("/api/v1/admin/mock/recording/save", post(save_mock_recording))
+        
+        // Per-organization encryption at rest
+        .route("/api/v1/admin/encryption", get(get_encryption_status))
+        .route("/api/v1/admin/orgs/:org_id/encryption", put(set_org_encryption))
+        .route("/api/v1/admin/orgs/:org_id/encryption/rotate", post(rotate_org_key))
+        
+        // Kafka and webhook ingestion sources
+        .route("/api/v1/admin/ingestion", get(get_ingestion_status))
+        .route("/api/v1/admin/ingestion/quarantine", get(get_quarantined_messages))
+        .route("/api/v1/admin/ingestion/:source/pause", post(pause_ingestion))
+        .route("/api/v1/admin/ingestion/:source/resume", post(resume_ingestion))
+        
+        // The server rebuilt at a past instant
+        .route("/api/v1/debug/at", get(get_state_at))
+        
+        // Degraded mode while every provider is down
+        .route("/api/v1/admin/degraded", get(get_degraded))
+        .route("/api/v1/admin/degraded/enter", post(enter_degraded))
+        .route("/api/v1/admin/degraded/exit", post(exit_degraded))
+        
+        // Hot and cold memory tiers
+        .route("/api/v1/admin/tiering", get(get_tiering))
+        .route("/api/v1/admin/tiering/run", post(run_tiering))
+        
+        // Shadow traffic to staging and its off switch
+        .route("/api/v1/admin/shadow", get(get_shadow).post(set_shadow))
+        .route("/api/v1/admin/shadow/chains/:id", get(get_shadow_chain))
+        
+        // Poll times and stalls of signal tasks, and the profiling switch
+        .route("/api/v1/admin/profiling", get(get_profiling).post(set_profiling))
+        
+        // Usage files for billing and the manifest of delivered ones
+        .route("/api/v1/admin/billing/exports", get(get_billing_exports))
+        .route("/api/v1/admin/billing/exports/run", post(run_billing_export))
+        
+        // Summaries of memory namespaces near their quota
+        .route("/api/v1/admin/summarization", get(get_summarization))
+        .route("/api/v1/admin/summarization/run", post(run_summarization))
+        
+        // Certificates of the distributed network, reloaded from disk
+        .route("/api/v1/network/tls/reload", post(reload_network_tls))
+        
+        // Quality benchmarks and runs, which spend provider tokens
+        .route("/api/v1/quality/benchmarks", post(create_quality_benchmark))
+        .route("/api/v1/quality/benchmarks/:id", delete(delete_quality_benchmark))
+        .route("/api/v1/quality/runs", post(run_quality_benchmarks))
+        
+        // Dead letters sent through again, as from the admin UI
+        .route("/api/v1/dead-letters/:id/retry", post(retry_dead_letter));
+    if let Some(auth_state) = auth_state.clone() {
+        admin_router = admin_router
+            .route_layer(middleware::from_fn(require_permission(Permission::SystemAdmin)))
+            .route_layer(middleware::from_fn_with_state(auth_state, auth_mw));
+    }
+    
+    // Memory dumps; callers need a token when auth is enabled, and a dump
+    // holds only their organization's entries
+    let mut memory_dump_router = Router::new()
+        .route("/api/v1/memory/export", get(export_memory))
+        .route(
+            "/api/v1/memory/import",
+            post(import_memory).layer(DefaultBodyLimit::max(MAX_MEMORY_DUMP_BYTES)),
+        );
+    if let Some(auth_state) = auth_state.clone() {
+        memory_dump_router = memory_dump_router.route_layer(middleware::from_fn_with_state(auth_state, auth_mw));
+    }
+    
+    // Tag spend, charged to the caller's privacy budget; callers need a
+    // token when auth is enabled
+    let mut tag_spend_router = Router::new().route("/api/v1/costs/by-tag", get(get_costs_by_tag));
+    if let Some(auth_state) = auth_state.clone() {
+        tag_spend_router = tag_spend_router.route_layer(middleware::from_fn_with_state(auth_state, auth_mw));
+    }
+    
+    // Review of pending memory writes, for callers who may modify memory
+    let mut moderation_router = Router::new()
+        .route("/api/v1/memory/pending", get(list_pending_memory))
+        .route("/api/v1/memory/pending/:id/approve", post(approve_memory))
+        .route("/api/v1/memory/pending/:id/reject", post(reject_memory));
+    if let Some(auth_state) = auth_state.clone() {
+        moderation_router = moderation_router
+            .route_layer(middleware::from_fn(require_permission(Permission::ModifyMemory)))
+            .route_layer(middleware::from_fn_with_state(auth_state, auth_mw));
+    }
+    
+    // Outputs held at approval gates, decided by system admins when auth
+    // is enabled; those in an organization see only its chains
+    let mut approvals_router = Router::new()
+        .route("/api/v1/approvals", get(list_approvals))
+        .route("/api/v1/approvals/:id/approve", post(approve_output))
+        .route("/api/v1/approvals/:id/reject", post(reject_output));
+    if let Some(auth_state) = auth_state.clone() {
+        approvals_router = approvals_router
+            .route_layer(middleware::from_fn(require_permission(Permission::SystemAdmin)))
+            .route_layer(middleware::from_fn_with_state(auth_state, auth_mw));
+    }
+    
     let mut router = Router::new()
         // Health check endpoints (no auth)
         .route("/health", get(health_check_simple))
+        .route("/health/detail", get(health_check_detail))
         .route("/health/detailed", get(health_check_detailed))
         .route("/livez", get(liveness_probe))
         .route("/readyz", get(readiness_probe))
@@ -165,16 +372,79 @@ pub fn create_api_router(server: Arc<HAL9Server>) -> Router {
         // Core endpoints
         .route("/api/v1/status", get(get_status))
         .route("/api/v1/signal", post(submit_signal))
+        .route("/api/v1/signals", get(list_signals))
+        .route("/api/v1/signals/batch", post(submit_signal_batch))
         .route("/api/v1/signal/:id", get(get_signal_trace))
+        .route("/api/v1/chains/compare", get(compare_chains))
+        .route("/api/v1/chains/:id", get(get_chain_result))
+        .route("/api/v1/chains/:id/replay", post(replay_chain))
+        .route("/api/v1/signals/:id/visualization", get(visualize_chain))
+        .route("/api/v1/signals/:id/receipt", get(get_chain_receipt))
+        .route("/api/v1/metadata/schema", get(get_metadata_schema))
+        
+        // Per-user limits
+        .route("/api/v1/limits/me", get(get_my_limits))
+        
+        // API spend and budget period
+        .route("/api/v1/costs", get(get_costs))
+        .route("/api/v1/reports/prompt-composition", get(get_prompt_composition))
+        
+        // Memory
+        .route("/api/v1/memory/stats", get(get_memory_stats))
+        
+        // Emergence in signal flow
+        .route("/api/v1/consciousness/emergence", get(get_emergence_report))
+        
+        // Creative problem solving
+        .route("/api/v1/intelligence/create", post(create_solutions))
+        .route("/api/v1/goals", post(create_goal))
+        .route("/api/v1/goals/:id", get(get_goal))
+        
+        // Load-based self-organization
+        .route("/api/v1/scaling/events", get(get_scaling_events))
+        .route("/api/v1/scaling/recommendation", get(get_scaling_recommendation))
+        .route("/api/v1/scaling/drain", get(get_drain_status))
+        .route("/api/v1/dead-letters", get(get_dead_letters))
+        
+        // Webhook subscriptions
+        .route("/api/v1/webhooks", post(api_webhooks::create_webhook).get(api_webhooks::list_webhooks))
+        .route("/api/v1/webhooks/:id", delete(api_webhooks::delete_webhook))
+        .route("/api/v1/webhooks/:id/deliveries", get(api_webhooks::list_deliveries))
+        .route("/api/v1/webhooks/:id/test", post(api_webhooks::test_webhook))
+        
+        // Inbound webhooks of ingestion sources, verified by their HMAC
+        .route("/api/v1/ingest/webhooks/:source", post(receive_ingest_webhook))
+        
+        // Quality benchmarks, their runs and trends
+        .route("/api/v1/quality/benchmarks", get(list_quality_benchmarks))
+        .route("/api/v1/quality/runs", get(list_quality_runs))
+        .route("/api/v1/quality/runs/:id", get(get_quality_run))
+        .route("/api/v1/quality/trends", get(get_quality_trends))
+        
+        // Chain templates and the files submitted to them
+        .route("/api/v1/templates", get(list_templates).post(create_template))
+        .route("/api/v1/templates/:name", get(get_template))
+        .route("/api/v1/templates/:name/versions", get(list_template_versions))
+        .route("/api/v1/templates/:name/submit", post(submit_template))
+        .route("/api/v1/artifacts/:id", get(get_artifact))
+        
+        // Multi-turn sessions, their accumulated context and its reset
+        .route("/api/v1/sessions", get(list_sessions).post(create_session))
+        .route("/api/v1/sessions/:id", get(get_session).delete(delete_session))
+        .route("/api/v1/sessions/:id/reset", post(reset_session))
         
         // Neuron management
         .route("/api/v1/neurons", get(list_neurons))
+        .route("/api/v1/neurons/changes", get(get_neuron_changes))
+        .route("/api/v1/neurons/changes/stream", get(neuron_changes_websocket))
         .route("/api/v1/neurons/:id", get(get_neuron))
         .route("/api/v1/neurons/:id/health", get(get_neuron_health))
         
         // Metrics
         .route("/api/v1/metrics", get(get_metrics))
         .route("/api/v1/metrics/export", get(export_metrics))
+        .route("/api/v1/plugins/:name/stats", get(get_plugin_stats))
+        .route("/api/v1/slo/status", get(get_slo_status))
         
         // Prometheus metrics endpoint
         .route("/metrics", get(prometheus_metrics))
@@ -182,32 +452,61 @@ pub fn create_api_router(server: Arc<HAL9Server>) -> Router {
         // Network status
         .route("/api/v1/network/status", get(get_network_status))
         
-        // WebSocket endpoint for real-time updates
+        // Signal stream over WebSocket, and over SSE where proxies block upgrades
         .route("/api/v1/ws", get(websocket_handler))
+        .route("/api/v1/signals/stream", get(websocket_handler))
+        .route("/api/v1/signals/stream.sse", get(event_stream::signal_stream_sse))
         
         // Error debugging endpoints (admin only)
         .route("/api/v1/errors/recent", get(get_recent_errors))
         .route("/api/v1/errors/:id", get(get_error_details))
         
+        .merge(memory_dump_router)
+        .merge(tag_spend_router)
+        .merge(moderation_router)
+        .merge(approvals_router)
+        .merge(admin_router)
+        
+        // Copy sampled submissions to staging once answered; innermost, so
+        // it sees the response production gave
+        .layer(middleware::from_fn_with_state(server.shadow(), shadow_middleware))
         // Add CORS support
         .layer(CorsLayer::permissive())
         // Add rate limiting as extension
         .layer(axum::Extension(rate_limiter))
         // Add error store
         .layer(axum::Extension(error_store.clone()))
+        // Add idempotency key cache
+        .layer(axum::Extension(idempotency))
         // Add error recovery middleware
         .layer(middleware::from_fn(error_recovery_middleware))
         // Add request/response logging
         .layer(middleware::from_fn(logging_middleware))
         .with_state(server.clone());
     
+    // Identify callers on core routes so limits can be charged per user
+    if let Some(auth_state) = auth_state.clone() {
+        router = router.layer(middleware::from_fn_with_state(auth_state, optional_auth_mw));
+    }
+    
+    // CPU profiles, at the path pprof tooling expects; system admins only
+    // when auth is enabled
+    let mut pprof_router = Router::new().route("/debug/pprof/profile", get(get_cpu_profile));
+    if let Some(auth_state) = auth_state.clone() {
+        pprof_router = pprof_router
+            .route_layer(middleware::from_fn(require_permission(Permission::SystemAdmin)))
+            .route_layer(middleware::from_fn_with_state(auth_state, auth_mw));
+    }
+    router = router.merge(pprof_router.with_state(server.clone()));
+    
+    // Embedded admin UI; its session needs a token when auth is enabled
+    #[cfg(feature = "admin-ui")]
+    {
+        router = router.merge(crate::admin_ui::router(auth_state.clone()));
+    }
+    
     // Add authentication routes if enabled
-    if server.jwt_manager.is_some() {
-        let auth_state = AuthState {
-            jwt_manager: server.jwt_manager.clone().unwrap(),
-            api_key_manager: server.api_key_manager.clone().unwrap(),
-        };
-        
+    if let Some(auth_state) = auth_state {
         let api_auth_state = Arc::new(api_auth::AuthApiState {
             user_manager: server.user_manager.clone().unwrap(),
             jwt_manager: server.jwt_manager.clone().unwrap(),
@@ -229,10 +528,23 @@ pub fn create_api_router(server: Arc<HAL9Server>) -> Router {
             .route("/api/v1/auth/api-keys", get(api_auth::list_api_keys))
             .route("/api/v1/auth/api-keys/:id", put(api_auth::revoke_api_key))
             .route("/api/v1/auth/api-keys/:id", delete(api_auth::delete_api_key))
+            .route("/api/v1/keys", post(api_auth::create_api_key))
+            .route("/api/v1/keys", get(api_auth::list_api_keys))
+            .route("/api/v1/keys/:id", get(api_auth::get_api_key))
+            .route("/api/v1/keys/:id", delete(api_auth::delete_api_key))
+            .route("/api/v1/keys/:id/rotate", post(api_auth::rotate_api_key))
+            .route("/api/v1/keys/:id/revoke", post(api_auth::revoke_api_key))
             .layer(middleware::from_fn_with_state(auth_state.clone(), auth_mw))
             .with_state(api_auth_state);
         
-        router = router.merge(auth_router).merge(protected_auth_router);
+        // Organization routes are for the organization's admins
+        let org_router = Router::new()
+            .route("/api/v1/orgs/:id/usage", get(api_orgs::get_org_usage))
+            .route_layer(middleware::from_fn(require_permission(Permission::ViewOrgUsage)))
+            .route_layer(middleware::from_fn_with_state(auth_state.clone(), auth_mw))
+            .with_state(server.clone());
+        
+        router = router.merge(auth_router).merge(protected_auth_router).merge(org_router);
     }
     
     // Add code generation routes if configured
@@ -244,18 +556,28 @@ pub fn create_api_router(server: Arc<HAL9Server>) -> Router {
         .route("/api/v1/codegen/health", get(api_codegen::codegen_health))
         .route("/api/v1/codegen/templates", get(api_codegen::list_templates))
         .route("/api/v1/codegen/project", post(api_codegen::generate_project))
-        .route("/api/v1/codegen/project/:id", get(api_codegen::get_project_status))
         .route("/api/v1/codegen/complete", post(api_codegen::code_completion))
         .route("/api/v1/codegen/review", post(api_codegen::review_code))
         .route("/api/v1/codegen/refactor", post(api_codegen::refactor_code))
-        .with_state(codegen_state);
+        .with_state(codegen_state)
+        .merge(api_codegen::job_routes(server.codegen_jobs()));
     
     router = router.merge(codegen_router);
     
+    // Add MCP endpoint (streamable HTTP transport)
+    let mcp_state = Arc::new(api_mcp::McpState::new(server.clone()));
+    let mcp_router = Router::new()
+        .route("/mcp", post(api_mcp::mcp_post).get(api_mcp::mcp_get))
+        .with_state(mcp_state);
+    
+    router = router.merge(mcp_router);
+    
     // Add Genius Game routes
+    let game_connections = Arc::new(crate::genius_game::GameConnections::default());
+    server.metrics().set_game_connections(game_connections.clone());
     let genius_state = Arc::new(RwLock::new(crate::genius_game::AppState {
         games: HashMap::new(),
-        connections: HashMap::new(),
+        connections: game_connections,
     }));
     
     let genius_router = crate::genius_game::create_genius_game_router(genius_state);
@@ -285,7 +607,9 @@ pub fn create_api_router(server: Arc<HAL9Server>) -> Router {
         router = router.merge(graphql_routes(graphql_schema));
     }
     
-    router
+    // Refuse writes while read-only; outermost, so the maintenance error
+    // reaches clients as is and covers every merged router
+    router.layer(middleware::from_fn_with_state(server.read_only(), read_only_middleware))
 }
 
 // Handler implementations
@@ -311,198 +635,1174 @@ async fn get_status(
             average_latency_ms: calculate_average_latency(&status.metrics),
         },
         network_status: status.network_status,
+        paused_layers: status.paused_layers,
+        degraded: status.degraded,
+        read_only: status.read_only,
     };
     
     Ok(Json(ApiResponse::success(response)))
 }
 
-async fn submit_signal(
-    State(server): State<Arc<HAL9Server>>,
-    Json(req): Json<SubmitSignalRequest>,
-) -> Result<impl IntoResponse, ServerError> {
-    // Parse layer
-    let layer_str = match req.layer.to_lowercase().as_str() {
-        "l4" | "strategic" => "L4",
-        "l3" | "design" => "L3",
-        "l2" | "implementation" => "L2",
-        "l1" | "execution" => "L1",
-        _ => return Ok(Json(ApiResponse::error("Invalid layer specified"))),
-    };
+/// Limit mode from `var`, local unless set to "distributed"
+fn rate_limit_mode(var: &str) -> LimitMode {
+    match std::env::var(var) {
+        Ok(value) => value.parse().unwrap_or_else(|e| {
+            tracing::warn!("{}: {}", var, e);
+            LimitMode::Local
+        }),
+        Err(_) => LimitMode::Local,
+    }
+}
+
+/// Signal for a submission request, or why it can't be built
+fn build_signal(req: SubmitSignalRequest) -> Result<NeuronSignal, String> {
+    let layer_str = parse_layer(&req.layer).ok_or("Invalid layer specified")?;
     
-    // Create signal
-    let signal = NeuronSignal::forward(
+    let mut signal = NeuronSignal::forward(
         "api-client",
         &req.neuron_id.unwrap_or_else(|| format!("neuron-{}", layer_str.to_lowercase())),
         "API",
         layer_str,
         req.content,
     );
-    
-    // Submit to server
-    match server.submit_signal(signal).await {
-        Ok(signal_id) => Ok(Json(ApiResponse::success(serde_json::json!({
-            "signal_id": signal_id,
-            "message": "Signal submitted successfully"
-        })))),
-        Err(e) => Ok(Json(ApiResponse::error(format!("Failed to submit signal: {}", e)))),
+    signal.metadata = req.metadata;
+    if let Some(session_id) = req.session_id {
+        signal.metadata.insert(SESSION_ID_KEY.to_string(), session_id);
+    }
+    if let Some(format) = req.output_format {
+        request_format(&mut signal.metadata, format, req.output_schema.as_ref());
+    } else if req.output_schema.is_some() {
+        return Err("An output schema needs the json output format".to_string());
     }
+    if !req.tags.is_empty() {
+        cost_tags::check(&req.tags).map_err(|e| e.to_string())?;
+        signal.metadata.insert(TAGS_KEY.to_string(), cost_tags::encode(&req.tags));
+    }
+    Ok(signal)
 }
 
-async fn get_signal_trace(
-    State(_server): State<Arc<HAL9Server>>,
-    Path(_signal_id): Path<String>,
+async fn submit_signal(
+    State(server): State<Arc<HAL9Server>>,
+    Extension(idempotency): Extension<Arc<IdempotencyCache>>,
+    user: Option<Extension<AuthUser>>,
+    headers: HeaderMap,
+    Json(req): Json<SubmitSignalRequest>,
 ) -> Result<impl IntoResponse, ServerError> {
-    // This would require implementing signal tracing in the server
-    // For now, return a placeholder
-    Ok(Json(ApiResponse::<SignalTrace>::error("Signal tracing not yet implemented")))
+    let owner = user.map(|Extension(user)| ChainOwner::from(&user));
+    
+    // A retry of a submission that already went through gets the same answer
+    let key = IdempotencyCache::key_for(&headers, "signal", owner.as_ref());
+    if let Some(response) = key.as_deref().and_then(|key| idempotency.get::<SubmitSignalResponse>(key)) {
+        return Ok(Json(ApiResponse::success(response)));
+    }
+    
+    let signal = match build_signal(req) {
+        Ok(signal) => signal,
+        Err(msg) => return Ok(Json(ApiResponse::error(msg))),
+    };
+    
+    // Submit to server, charged to the caller if known
+    match server.submit_signal_as(owner.as_ref(), signal).await {
+        Ok(signal_id) => {
+            let response = SubmitSignalResponse {
+                chain_id: signal_id.clone(),
+                signal_id,
+                message: "Signal submitted successfully".to_string(),
+            };
+            if let Some(key) = key {
+                idempotency.insert(key, &response);
+            }
+            Ok(Json(ApiResponse::success(response)))
+        }
+        Err(e @ (ServerError::LimitExceeded(_) | ServerError::InvalidInput(_) | ServerError::Draining)) => Err(e),
+        Err(e) => Ok(Json(ApiResponse::error(format!("Failed to submit signal: {}", e)))),
+    }
 }
 
-async fn list_neurons(
+async fn submit_signal_batch(
     State(server): State<Arc<HAL9Server>>,
+    Extension(idempotency): Extension<Arc<IdempotencyCache>>,
+    user: Option<Extension<AuthUser>>,
+    headers: HeaderMap,
+    Json(req): Json<BatchSubmitRequest>,
 ) -> Result<impl IntoResponse, ServerError> {
-    let neurons = server.list_neurons().await?;
-    Ok(Json(ApiResponse::success(neurons)))
+    if req.signals.is_empty() {
+        return Err(ServerError::InvalidInput("Batch contains no signals".to_string()));
+    }
+    if req.signals.len() > MAX_BATCH_SIZE {
+        return Err(ServerError::InvalidInput(format!(
+            "Batch of {} signals exceeds the limit of {}", req.signals.len(), MAX_BATCH_SIZE
+        )));
+    }
+    
+    let owner = user.map(|Extension(user)| ChainOwner::from(&user));
+    let key = IdempotencyCache::key_for(&headers, "signals/batch", owner.as_ref());
+    if let Some(response) = key.as_deref().and_then(|key| idempotency.get::<BatchSubmitResponse>(key)) {
+        return Ok(Json(ApiResponse::success(response)));
+    }
+    
+    // Signals are submitted in order; one failing doesn't stop the rest
+    let mut results = Vec::with_capacity(req.signals.len());
+    for (index, signal) in req.signals.into_iter().enumerate() {
+        let outcome = match build_signal(signal) {
+            Ok(signal) => server.submit_signal_as(owner.as_ref(), signal).await.map_err(|e| e.to_string()),
+            Err(msg) => Err(msg),
+        };
+        results.push(match outcome {
+            Ok(signal_id) => BatchItemResult { index, signal_id: Some(signal_id), error: None },
+            Err(error) => BatchItemResult { index, signal_id: None, error: Some(error) },
+        });
+    }
+    
+    let accepted = results.iter().filter(|r| r.signal_id.is_some()).count();
+    let response = BatchSubmitResponse {
+        rejected: results.len() - accepted,
+        accepted,
+        results,
+    };
+    if let Some(key) = key {
+        idempotency.insert(key, &response);
+    }
+    Ok(Json(ApiResponse::success(response)))
 }
 
-async fn get_neuron(
+async fn get_costs(
     State(server): State<Arc<HAL9Server>>,
-    Path(neuron_id): Path<String>,
 ) -> Result<impl IntoResponse, ServerError> {
-    match server.get_neuron_info(&neuron_id).await {
-        Ok(info) => Ok(Json(ApiResponse::success(info))),
-        Err(e) => Ok(Json(ApiResponse::error(format!("Neuron not found: {}", e)))),
-    }
+    Ok(Json(ApiResponse::success(server.cost_stats().await)))
 }
 
-async fn get_neuron_health(
+#[derive(Debug, Deserialize)]
+struct CostsByTagQuery {
+    /// Tag key to group spend by
+    key: String,
+    /// `day`, `week` or `month`
+    #[serde(default = "default_cost_period")]
+    period: String,
+    /// `json`, the only format noised reports come in
+    format: Option<String>,
+    /// Epsilon of the differential privacy noise, charged to the caller's
+    /// privacy budget; `report_privacy.default_epsilon` if not given
+    dp_epsilon: Option<f64>,
+}
+
+fn default_cost_period() -> String {
+    "month".to_string()
+}
+
+async fn get_costs_by_tag(
     State(server): State<Arc<HAL9Server>>,
-    Path(neuron_id): Path<String>,
+    user: Option<Extension<AuthUser>>,
+    Query(query): Query<CostsByTagQuery>,
 ) -> Result<impl IntoResponse, ServerError> {
-    match server.get_neuron_health(&neuron_id).await {
-        Ok(health) => Ok(Json(ApiResponse::success(health))),
-        Err(e) => Ok(Json(ApiResponse::error(format!("Failed to get health: {}", e)))),
+    let caller = report_privacy::require_caller(user.as_deref())?;
+    let period = parse_period(&query.period).map_err(|e| ServerError::InvalidInput(e.to_string()))?;
+    if let Some(format) = query.format.as_deref().filter(|format| *format != "json") {
+        return Err(ServerError::InvalidInput(format!(
+            "Unknown format '{}': reports are released with privacy noise, as json only",
+            format
+        )));
     }
+    let report = server.cost_by_tag(&query.key, period)?;
+    let privacy = server.report_privacy();
+    let epsilon = query.dp_epsilon.unwrap_or_else(|| privacy.default_epsilon());
+    let report = privacy.release(&caller, epsilon, &report).await?;
+    Ok(Json(ApiResponse::success(report)))
 }
 
-async fn get_metrics(
+#[derive(Debug, Deserialize)]
+struct PromptCompositionQuery {
+    /// `day`, `week` or `month`
+    #[serde(default = "default_cost_period")]
+    period: String,
+}
+
+async fn get_prompt_composition(
     State(server): State<Arc<HAL9Server>>,
+    Query(query): Query<PromptCompositionQuery>,
 ) -> Result<impl IntoResponse, ServerError> {
-    let metrics = server.get_metrics().await?;
-    Ok(Json(ApiResponse::success(metrics)))
+    let period = parse_period(&query.period).map_err(|e| ServerError::InvalidInput(e.to_string()))?;
+    Ok(Json(ApiResponse::success(server.prompt_composition_report(period))))
 }
 
-async fn export_metrics(
+async fn get_memory_stats(
     State(server): State<Arc<HAL9Server>>,
-    Query(params): Query<std::collections::HashMap<String, String>>,
 ) -> Result<impl IntoResponse, ServerError> {
-    let format = params.get("format").map(|s| s.as_str()).unwrap_or("json");
-    
-    match format {
-        "json" => {
-            let metrics = server.get_metrics().await?;
-            Ok(Json(metrics).into_response())
-        }
-        "prometheus" => {
-            let prometheus_data = crate::prometheus_exporter::export_metrics(server).await;
-            Ok((StatusCode::OK, prometheus_data).into_response())
-        }
-        _ => Ok(Json(ApiResponse::<String>::error("Unsupported format")).into_response()),
+    let stats = server.memory_stats().await?;
+    Ok(Json(ApiResponse::success(serde_json::json!({ "namespaces": stats }))))
+}
+
+/// Largest memory dump accepted by the import endpoint
+const MAX_MEMORY_DUMP_BYTES: usize = 256 * 1024 * 1024;
+
+#[derive(Debug, Deserialize)]
+struct MemoryExportQuery {
+    namespace: Option<String>,
+    neuron: Option<String>,
+}
+
+/// Entries `user` may move with `permission`: all of them without auth or
+/// for a system admin outside any organization, otherwise those of the
+/// user's organization
+fn dump_scope(user: Option<&AuthUser>, permission: Permission) -> Result<DumpScope, ServerError> {
+    let Some(user) = user else {
+        return Ok(DumpScope::All);
+    };
+    if !user.permissions.has(&permission) {
+        return Err(ServerError::Forbidden(format!("Memory dumps need the {:?} permission", permission)));
     }
+    Ok(match &user.org_id {
+        None if user.permissions.has(&Permission::SystemAdmin) => DumpScope::All,
+        org_id => DumpScope::Org(org_id.clone()),
+    })
 }
 
-async fn prometheus_metrics(
+async fn export_memory(
     State(server): State<Arc<HAL9Server>>,
+    user: Option<Extension<AuthUser>>,
+    Query(query): Query<MemoryExportQuery>,
 ) -> Result<impl IntoResponse, ServerError> {
-    let prometheus_data = crate::prometheus_exporter::export_metrics(server).await;
+    let scope = dump_scope(user.as_deref(), Permission::ViewMemory)?;
+    let lines = server.export_memory(query.namespace.as_deref(), query.neuron.as_deref(), &scope).await?;
+    let body = Body::from_stream(futures::stream::iter(
+        lines.into_iter().map(|line| Ok::<_, std::convert::Infallible>(line + "\n")),
+    ));
     Ok((
-        StatusCode::OK,
-        [(axum::http::header::CONTENT_TYPE, "text/plain; version=0.0.4")],
-        prometheus_data,
+        [
+            (axum::http::header::CONTENT_TYPE, "application/x-ndjson"),
+            (axum::http::header::CONTENT_DISPOSITION, "attachment; filename=\"hal9-memory.jsonl\""),
+        ],
+        body,
     ))
 }
 
-async fn get_network_status(
+async fn import_memory(
     State(server): State<Arc<HAL9Server>>,
+    user: Option<Extension<AuthUser>>,
+    Query(options): Query<ImportOptions>,
+    dump: String,
 ) -> Result<impl IntoResponse, ServerError> {
-    match server.network_status().await {
-        Some(status) => Ok(Json(ApiResponse::success(status))),
-        None => {
-            let disabled_status = crate::server::NetworkStatus {
-                enabled: false,
-                server_id: String::new(),
-                connected_servers: vec![],
-                remote_neurons: 0,
-            };
-            Ok(Json(ApiResponse::success(disabled_status)))
-        }
-    }
+    let scope = dump_scope(user.as_deref(), Permission::ModifyMemory)?;
+    let report = server.import_memory(&dump, &options, &scope).await?;
+    Ok(Json(ApiResponse::success(report)))
 }
 
-async fn websocket_handler(
-    ws: axum::extract::ws::WebSocketUpgrade,
+async fn list_pending_memory(
     State(server): State<Arc<HAL9Server>>,
-) -> impl IntoResponse {
-    ws.on_upgrade(|socket| handle_websocket(socket, server))
+    Query(query): Query<HashMap<String, String>>,
+) -> Result<impl IntoResponse, ServerError> {
+    let params = ListParams::parse::<MemoryEntry>(&query, &[])?;
+    let page = paginate(server.pending_memory().await?, &params)?;
+    Ok(Json(ApiResponse::success(page)))
 }
 
-async fn handle_websocket(
-    mut socket: axum::extract::ws::WebSocket,
-    server: Arc<HAL9Server>,
-) {
-    use axum::extract::ws::Message;
-    
-    // Subscribe to server events
-    let mut event_rx = server.subscribe_to_events().await;
-    
-    // Send initial connection message
-    let welcome_msg = WsMessage::ServerEvent {
-        event: "connected".to_string(),
-        details: "Connected to 2HAL9 server".to_string(),
-    };
-    
-    if let Ok(msg_json) = serde_json::to_string(&welcome_msg) {
-        let _ = socket.send(Message::Text(msg_json)).await;
-    }
-    
-    // Handle bidirectional communication
-    loop {
-        tokio::select! {
-            // Handle incoming messages from client
-            Some(msg) = socket.recv() => {
-                match msg {
-                    Ok(msg) => match msg {
-                    Message::Text(text) => {
-                        // Handle text messages if needed
-                        tracing::debug!("Received WebSocket message: {}", text);
-                    }
-                    Message::Close(_) => {
-                        tracing::info!("WebSocket client disconnected");
-                        break;
-                    }
-                    _ => {}
-                    },
-                    Err(_) => break,
-                }
-            }
-            
-            // Forward server events to client
-            Ok(event) = event_rx.recv() => {
-                if let Ok(msg_json) = serde_json::to_string(&event) {
-                    if socket.send(Message::Text(msg_json)).await.is_err() {
-                        break;
-                    }
-                }
-            }
-        }
+fn memory_entry_id(id: &str) -> Result<uuid::Uuid, ServerError> {
+    uuid::Uuid::parse_str(id).map_err(|_| ServerError::InvalidInput(format!("Invalid memory entry id '{}'", id)))
+}
+
+async fn approve_memory(
+    State(server): State<Arc<HAL9Server>>,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, ServerError> {
+    let id = memory_entry_id(&id)?;
+    server.approve_memory(id).await?;
+    Ok(Json(ApiResponse::success(serde_json::json!({ "id": id, "review": "approved" }))))
+}
+
+/// Body of `POST /api/v1/memory/pending/:id/reject`
+#[derive(Debug, Deserialize)]
+struct RejectMemoryRequest {
+    reason: String,
+}
+
+async fn reject_memory(
+    State(server): State<Arc<HAL9Server>>,
+    Path(id): Path<String>,
+    Json(req): Json<RejectMemoryRequest>,
+) -> Result<impl IntoResponse, ServerError> {
+    let id = memory_entry_id(&id)?;
+    if req.reason.trim().is_empty() {
+        return Err(ServerError::InvalidInput("A rejection needs a reason".to_string()));
     }
+    server.reject_memory(id, req.reason.trim()).await?;
+    Ok(Json(ApiResponse::success(serde_json::json!({ "id": id, "review": "rejected", "reason": req.reason.trim() }))))
 }
 
-// Error handling
-impl IntoResponse for ServerError {
-    fn into_response(self) -> Response {
-        let (status, message) = match self {
+async fn get_emergence_report(
+    State(server): State<Arc<HAL9Server>>,
+) -> Result<impl IntoResponse, ServerError> {
+    let report = server.emergence_report().await?;
+    Ok(Json(ApiResponse::success(report)))
+}
+
+async fn create_solutions(
+    State(server): State<Arc<HAL9Server>>,
+    Json(req): Json<CreateSolutionsRequest>,
+) -> Result<impl IntoResponse, ServerError> {
+    let challenge = Challenge {
+        id: uuid::Uuid::new_v4(),
+        problem_statement: req.problem_statement,
+        context: req.context,
+        constraints: req.constraints,
+        evaluation_criteria: vec![],
+    };
+    let report = server.create_solutions(challenge).await?;
+    Ok(Json(ApiResponse::success(report)))
+}
+
+async fn create_goal(
+    State(server): State<Arc<HAL9Server>>,
+    user: Option<Extension<AuthUser>>,
+    Json(req): Json<CreateGoalRequest>,
+) -> Result<impl IntoResponse, ServerError> {
+    let goal = Goal {
+        id: uuid::Uuid::new_v4(),
+        description: req.description,
+        priority: req.priority,
+        constraints: req.constraints,
+        success_criteria: req.success_criteria,
+        decomposition_strategy: req.decomposition_strategy,
+    };
+    let owner = user.map(|Extension(user)| ChainOwner::from(&user));
+    let status = server.create_goal(goal, owner).await?;
+    Ok((StatusCode::CREATED, Json(ApiResponse::success(status))))
+}
+
+async fn get_goal(
+    State(server): State<Arc<HAL9Server>>,
+    Path(goal_id): Path<String>,
+) -> Result<impl IntoResponse, ServerError> {
+    let goal_id = uuid::Uuid::parse_str(&goal_id)
+        .map_err(|_| ServerError::InvalidInput(format!("Invalid goal ID: {}", goal_id)))?;
+    Ok(Json(ApiResponse::success(server.goal_status(goal_id)?)))
+}
+
+async fn get_scaling_events(
+    State(server): State<Arc<HAL9Server>>,
+    Query(query): Query<HashMap<String, String>>,
+) -> Result<impl IntoResponse, ServerError> {
+    let params = ListParams::parse::<ScalingEvent>(&query, &[])?;
+    let page = paginate(server.scaling_events().await, &params)?;
+    Ok(Json(ApiResponse::success(page)))
+}
+
+/// Replica count for the current load, given `?replicas=` currently running
+async fn get_scaling_recommendation(
+    State(server): State<Arc<HAL9Server>>,
+    Query(query): Query<HashMap<String, String>>,
+) -> Result<impl IntoResponse, ServerError> {
+    let replicas = match query.get("replicas") {
+        Some(replicas) => replicas.parse::<u32>()
+            .map_err(|_| ServerError::InvalidInput(format!("Invalid replica count '{}'", replicas)))?,
+        None => 1,
+    };
+    Ok(Json(ApiResponse::success(server.autoscaler().recommend(replicas))))
+}
+
+async fn get_drain_status(
+    State(server): State<Arc<HAL9Server>>,
+) -> Result<impl IntoResponse, ServerError> {
+    Ok(Json(ApiResponse::success(server.autoscaler().drain_status())))
+}
+
+async fn begin_drain(
+    State(server): State<Arc<HAL9Server>>,
+) -> Result<impl IntoResponse, ServerError> {
+    let autoscaler = server.autoscaler();
+    autoscaler.begin_drain();
+    Ok(Json(ApiResponse::success(autoscaler.drain_status())))
+}
+
+/// Body of `POST /api/v1/admin/readonly`
+#[derive(Debug, Deserialize)]
+struct SetReadOnlyRequest {
+    enabled: bool,
+    reason: Option<String>,
+    /// Retry hint given to refused requests; `read_only.retry_after_secs` by default
+    retry_after_secs: Option<u64>,
+}
+
+async fn get_read_only(
+    State(server): State<Arc<HAL9Server>>,
+) -> Result<impl IntoResponse, ServerError> {
+    Ok(Json(ApiResponse::success(server.read_only().status())))
+}
+
+async fn set_read_only(
+    State(server): State<Arc<HAL9Server>>,
+    user: Option<Extension<AuthUser>>,
+    Json(req): Json<SetReadOnlyRequest>,
+) -> Result<impl IntoResponse, ServerError> {
+    let actor = user.map(|Extension(user)| user.username);
+    let written = if req.enabled {
+        server.enable_read_only(req.reason, req.retry_after_secs, actor.as_deref());
+        0
+    } else {
+        server.disable_read_only(actor.as_deref()).await
+    };
+    Ok(Json(ApiResponse::success(serde_json::json!({
+        "status": server.read_only().status(),
+        "held_writes_written": written,
+    }))))
+}
+
+/// Body of `POST /api/v1/admin/shadow`
+#[derive(Debug, Deserialize)]
+struct SetShadowRequest {
+    enabled: bool,
+}
+
+async fn get_shadow(
+    State(server): State<Arc<HAL9Server>>,
+) -> Result<impl IntoResponse, ServerError> {
+    Ok(Json(ApiResponse::success(server.shadow().status())))
+}
+
+/// The emergency off switch, and turning shadowing back on
+async fn set_shadow(
+    State(server): State<Arc<HAL9Server>>,
+    user: Option<Extension<AuthUser>>,
+    Json(req): Json<SetShadowRequest>,
+) -> Result<impl IntoResponse, ServerError> {
+    let actor = user.map(|Extension(user)| user.username);
+    Ok(Json(ApiResponse::success(server.shadow().set_active(req.enabled, actor.as_deref()))))
+}
+
+/// The staging chain a production chain's shadow started
+async fn get_shadow_chain(
+    State(server): State<Arc<HAL9Server>>,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, ServerError> {
+    let record = server.shadow().record(&id).await
+        .map_err(|e| ServerError::Internal(e.to_string()))?
+        .ok_or_else(|| ServerError::NotFound(format!("Chain {} was not shadowed", id)))?;
+    Ok(Json(ApiResponse::success(record)))
+}
+
+/// Body of `POST /api/v1/admin/profiling`
+#[derive(Debug, Deserialize)]
+struct SetProfilingRequest {
+    enabled: bool,
+}
+
+async fn get_profiling(
+    State(server): State<Arc<HAL9Server>>,
+) -> Result<impl IntoResponse, ServerError> {
+    Ok(Json(ApiResponse::success(server.profiler().status())))
+}
+
+async fn set_profiling(
+    State(server): State<Arc<HAL9Server>>,
+    user: Option<Extension<AuthUser>>,
+    Json(req): Json<SetProfilingRequest>,
+) -> Result<impl IntoResponse, ServerError> {
+    let actor = user.map(|Extension(user)| user.username);
+    Ok(Json(ApiResponse::success(server.profiler().set_active(req.enabled, actor.as_deref()))))
+}
+
+#[derive(Debug, Deserialize)]
+struct CpuProfileQuery {
+    seconds: Option<u64>,
+}
+
+/// A pprof protobuf of the CPU samples taken over `seconds`
+async fn get_cpu_profile(
+    State(server): State<Arc<HAL9Server>>,
+    Query(query): Query<CpuProfileQuery>,
+) -> Result<impl IntoResponse, ServerError> {
+    let profile = server.profiler().cpu_profile(query.seconds).await?;
+    Ok((
+        StatusCode::OK,
+        [
+            (axum::http::header::CONTENT_TYPE, "application/octet-stream"),
+            (axum::http::header::CONTENT_DISPOSITION, "attachment; filename=\"profile.pb\""),
+        ],
+        profile,
+    ))
+}
+
+#[derive(Debug, Deserialize)]
+struct StateAtQuery {
+    /// RFC 3339
+    timestamp: String,
+}
+
+/// In-flight chains, queue depths, circuit breakers and budgets as they
+/// stood at `?timestamp=`
+async fn get_state_at(
+    State(server): State<Arc<HAL9Server>>,
+    Query(query): Query<StateAtQuery>,
+) -> Result<impl IntoResponse, ServerError> {
+    let at = chrono::DateTime::parse_from_rfc3339(&query.timestamp)
+        .map(|at| at.with_timezone(&chrono::Utc))
+        .map_err(|_| ServerError::InvalidInput(format!("Invalid timestamp '{}'; expected an RFC 3339 time", query.timestamp)))?;
+    Ok(Json(ApiResponse::success(server.state_at(at).await?)))
+}
+
+async fn get_retention(
+    State(server): State<Arc<HAL9Server>>,
+) -> Result<impl IntoResponse, ServerError> {
+    Ok(Json(ApiResponse::success(server.retention_status())))
+}
+
+async fn run_retention(
+    State(server): State<Arc<HAL9Server>>,
+) -> Result<impl IntoResponse, ServerError> {
+    Ok(Json(ApiResponse::success(server.run_retention().await?)))
+}
+
+async fn compact_databases(
+    State(server): State<Arc<HAL9Server>>,
+) -> Result<impl IntoResponse, ServerError> {
+    Ok(Json(ApiResponse::success(server.compact_databases().await?)))
+}
+
+#[derive(Debug, Deserialize)]
+struct BillingExportsQuery {
+    org_id: Option<String>,
+    /// First date listed, e.g. 2026-10-01
+    from: Option<chrono::NaiveDate>,
+    /// Last date listed
+    to: Option<chrono::NaiveDate>,
+}
+
+async fn get_billing_exports(
+    State(server): State<Arc<HAL9Server>>,
+    Query(query): Query<BillingExportsQuery>,
+) -> Result<impl IntoResponse, ServerError> {
+    let files = server.billing_manifest(query.org_id.as_deref(), query.from, query.to).await?;
+    Ok(Json(ApiResponse::success(files)))
+}
+
+#[derive(Debug, Deserialize)]
+struct RunBillingExportQuery {
+    /// A finished day to export; the lookback window without one
+    date: Option<chrono::NaiveDate>,
+}
+
+async fn run_billing_export(
+    State(server): State<Arc<HAL9Server>>,
+    Query(query): Query<RunBillingExportQuery>,
+) -> Result<impl IntoResponse, ServerError> {
+    Ok(Json(ApiResponse::success(server.run_billing_export(query.date).await?)))
+}
+
+async fn get_tiering(
+    State(server): State<Arc<HAL9Server>>,
+) -> Result<impl IntoResponse, ServerError> {
+    Ok(Json(ApiResponse::success(server.tiering_status().await?)))
+}
+
+async fn run_tiering(
+    State(server): State<Arc<HAL9Server>>,
+) -> Result<impl IntoResponse, ServerError> {
+    Ok(Json(ApiResponse::success(server.run_tiering().await?)))
+}
+
+async fn get_summarization(
+    State(server): State<Arc<HAL9Server>>,
+) -> Result<impl IntoResponse, ServerError> {
+    Ok(Json(ApiResponse::success(server.summarization_status().await?)))
+}
+
+async fn run_summarization(
+    State(server): State<Arc<HAL9Server>>,
+) -> Result<impl IntoResponse, ServerError> {
+    Ok(Json(ApiResponse::success(server.run_summarization().await?)))
+}
+
+/// Body of `PUT /api/v1/admin/timeouts/adaptive`
+#[derive(Debug, Deserialize)]
+struct SetAdaptiveTimeoutsRequest {
+    enabled: bool,
+}
+
+async fn get_timeouts(
+    State(server): State<Arc<HAL9Server>>,
+) -> Result<impl IntoResponse, ServerError> {
+    Ok(Json(ApiResponse::success(server.timeout_status())))
+}
+
+async fn set_adaptive_timeouts(
+    State(server): State<Arc<HAL9Server>>,
+    Json(req): Json<SetAdaptiveTimeoutsRequest>,
+) -> Result<impl IntoResponse, ServerError> {
+    Ok(Json(ApiResponse::success(server.set_adaptive_timeouts(req.enabled))))
+}
+
+async fn get_chaos(
+    State(server): State<Arc<HAL9Server>>,
+) -> Result<impl IntoResponse, ServerError> {
+    Ok(Json(ApiResponse::success(server.chaos_status())))
+}
+
+async fn inject_chaos(
+    State(server): State<Arc<HAL9Server>>,
+    user: Option<Extension<AuthUser>>,
+    Json(req): Json<ChaosRequest>,
+) -> Result<impl IntoResponse, ServerError> {
+    let actor = user.map(|Extension(user)| user.username);
+    let injection = server.inject_chaos(req, actor.as_deref())?;
+    Ok((StatusCode::CREATED, Json(ApiResponse::success(injection))))
+}
+
+async fn stop_chaos(
+    State(server): State<Arc<HAL9Server>>,
+    Path(id): Path<String>,
+    user: Option<Extension<AuthUser>>,
+) -> Result<impl IntoResponse, ServerError> {
+    let id = uuid::Uuid::parse_str(&id)
+        .map_err(|_| ServerError::InvalidInput(format!("Invalid chaos injection ID: {}", id)))?;
+    let actor = user.map(|Extension(user)| user.username);
+    Ok(Json(ApiResponse::success(server.stop_chaos(id, actor.as_deref())?)))
+}
+
+async fn clear_chaos(
+    State(server): State<Arc<HAL9Server>>,
+    user: Option<Extension<AuthUser>>,
+) -> Result<impl IntoResponse, ServerError> {
+    let actor = user.map(|Extension(user)| user.username);
+    Ok(Json(ApiResponse::success(serde_json::json!({
+        "stopped": server.clear_chaos(actor.as_deref()),
+    }))))
+}
+
+async fn get_mock_scenarios(
+    State(server): State<Arc<HAL9Server>>,
+) -> Result<impl IntoResponse, ServerError> {
+    Ok(Json(ApiResponse::success(server.mock_scenarios())))
+}
+
+/// Body of `PUT /api/v1/admin/mock/scenario`; no name stops the replay
+#[derive(Debug, Deserialize)]
+struct SelectMockScenarioRequest {
+    name: Option<String>,
+    strict: Option<bool>,
+}
+
+async fn select_mock_scenario(
+    State(server): State<Arc<HAL9Server>>,
+    Json(req): Json<SelectMockScenarioRequest>,
+) -> Result<impl IntoResponse, ServerError> {
+    Ok(Json(ApiResponse::success(server.select_mock_scenario(req.name.as_deref(), req.strict)?)))
+}
+
+async fn save_mock_recording(
+    State(server): State<Arc<HAL9Server>>,
+) -> Result<impl IntoResponse, ServerError> {
+    Ok(Json(ApiResponse::success(server.save_mock_recording()?)))
+}
+
+async fn get_encryption_status(
+    State(server): State<Arc<HAL9Server>>,
+) -> Result<impl IntoResponse, ServerError> {
+    Ok(Json(ApiResponse::success(server.encryption_status().await?)))
+}
+
+/// Body of `PUT /api/v1/admin/orgs/:org_id/encryption`
+#[derive(Debug, Deserialize)]
+struct SetOrgEncryptionRequest {
+    enabled: bool,
+}
+
+async fn set_org_encryption(
+    State(server): State<Arc<HAL9Server>>,
+    Path(org_id): Path<String>,
+    Json(req): Json<SetOrgEncryptionRequest>,
+) -> Result<impl IntoResponse, ServerError> {
+    Ok(Json(ApiResponse::success(server.set_org_encryption(&org_id, req.enabled).await?)))
+}
+
+async fn rotate_org_key(
+    State(server): State<Arc<HAL9Server>>,
+    Path(org_id): Path<String>,
+) -> Result<impl IntoResponse, ServerError> {
+    Ok(Json(ApiResponse::success(server.rotate_org_key(&org_id).await?)))
+}
+
+async fn get_dead_letters(
+    State(server): State<Arc<HAL9Server>>,
+    Query(query): Query<HashMap<String, String>>,
+) -> Result<impl IntoResponse, ServerError> {
+    let params = ListParams::parse::<DeadLetter>(&query, &[])?;
+    let page = paginate(server.dead_letters(), &params)?;
+    Ok(Json(ApiResponse::success(page)))
+}
+
+/// Whether `user` may see and decide on `approval`: anyone without auth,
+/// a system admin outside any organization, or one of the organization
+/// owning the approval's chain
+fn may_decide(user: Option<&AuthUser>, approval: &PendingApproval) -> bool {
+    match user {
+        None => true,
+        Some(user) => match &user.org_id {
+            None => user.permissions.has(&Permission::SystemAdmin),
+            org_id => *org_id == approval.org_id,
+        },
+    }
+}
+
+/// Refuse `user` a decision on the approval `id` of another organization
+fn check_approval_scope(server: &HAL9Server, user: Option<&AuthUser>, id: &str) -> Result<(), ServerError> {
+    let approval = server
+        .pending_approval(id)
+        .ok_or_else(|| ServerError::NotFound(format!("Approval {} not found", id)))?;
+    if !may_decide(user, &approval) {
+        return Err(ServerError::Forbidden(format!("Approval {} belongs to another organization", id)));
+    }
+    Ok(())
+}
+
+async fn list_approvals(
+    State(server): State<Arc<HAL9Server>>,
+    user: Option<Extension<AuthUser>>,
+    Query(query): Query<HashMap<String, String>>,
+) -> Result<impl IntoResponse, ServerError> {
+    let params = ListParams::parse::<PendingApproval>(&query, &[])?;
+    let approvals = server
+        .pending_approvals()
+        .into_iter()
+        .filter(|approval| may_decide(user.as_deref(), approval))
+        .collect();
+    let page = paginate(approvals, &params)?;
+    Ok(Json(ApiResponse::success(page)))
+}
+
+/// Body of `POST /api/v1/approvals/:id/approve`
+#[derive(Debug, Default, Deserialize)]
+struct ApproveOutputRequest {
+    /// Replaces the content the held signals carry downstream
+    content: Option<String>,
+}
+
+async fn approve_output(
+    State(server): State<Arc<HAL9Server>>,
+    Path(id): Path<String>,
+    user: Option<Extension<AuthUser>>,
+    req: Option<Json<ApproveOutputRequest>>,
+) -> Result<impl IntoResponse, ServerError> {
+    let Json(req) = req.unwrap_or_default();
+    check_approval_scope(&server, user.as_deref(), &id)?;
+    let actor = user.map(|Extension(user)| user.username);
+    let decision = server.approve_output(&id, req.content, actor.as_deref()).await?;
+    Ok(Json(ApiResponse::success(decision)))
+}
+
+/// Body of `POST /api/v1/approvals/:id/reject`
+#[derive(Debug, Default, Deserialize)]
+struct RejectOutputRequest {
+    reason: Option<String>,
+}
+
+async fn reject_output(
+    State(server): State<Arc<HAL9Server>>,
+    Path(id): Path<String>,
+    user: Option<Extension<AuthUser>>,
+    req: Option<Json<RejectOutputRequest>>,
+) -> Result<impl IntoResponse, ServerError> {
+    let Json(req) = req.unwrap_or_default();
+    check_approval_scope(&server, user.as_deref(), &id)?;
+    let actor = user.map(|Extension(user)| user.username);
+    let decision = server.reject_output(&id, req.reason.as_deref(), actor.as_deref())?;
+    Ok(Json(ApiResponse::success(decision)))
+}
+
+async fn retry_dead_letter(
+    State(server): State<Arc<HAL9Server>>,
+    Path(signal_id): Path<String>,
+    user: Option<Extension<AuthUser>>,
+) -> Result<impl IntoResponse, ServerError> {
+    let owner = user.map(|Extension(user)| ChainOwner::from(&user));
+    let chain_id = server.retry_dead_letter(owner.as_ref(), &signal_id).await?;
+    Ok(Json(ApiResponse::success(serde_json::json!({
+        "chain_id": chain_id,
+        "retry_of": signal_id,
+    }))))
+}
+
+async fn get_metadata_schema(
+    State(server): State<Arc<HAL9Server>>,
+) -> Result<impl IntoResponse, ServerError> {
+    Ok(Json(ApiResponse::success(server.metadata_schema())))
+}
+
+async fn get_my_limits(
+    State(server): State<Arc<HAL9Server>>,
+    user: Option<Extension<AuthUser>>,
+) -> Result<impl IntoResponse, ServerError> {
+    let owner = match user {
+        Some(Extension(user)) => ChainOwner::from(&user),
+        None => ChainOwner {
+            user_id: "anonymous".to_string(),
+            role: "guest".to_string(),
+            org_id: None,
+            api_key_id: None,
+        },
+    };
+    
+    Ok(Json(ApiResponse::success(server.limit_usage(&owner))))
+}
+
+async fn get_signal_trace(
+    State(_server): State<Arc<HAL9Server>>,
+    Path(_signal_id): Path<String>,
+) -> Result<impl IntoResponse, ServerError> {
+    // This would require implementing signal tracing in the server
+    // For now, return a placeholder
+    Ok(Json(ApiResponse::<SignalTrace>::error("Signal tracing not yet implemented")))
+}
+
+/// Signals processed recently, `?propagation=backward` for gradient
+/// activity and `?since=` (RFC 3339) to bound how far back
+async fn list_signals(
+    State(server): State<Arc<HAL9Server>>,
+    Query(query): Query<HashMap<String, String>>,
+) -> Result<impl IntoResponse, ServerError> {
+    let since = query.get("since")
+        .map(|since| {
+            chrono::DateTime::parse_from_rfc3339(since)
+                .map(|since| since.with_timezone(&chrono::Utc))
+                .map_err(|_| ServerError::InvalidInput(format!("Invalid since '{}'; expected an RFC 3339 time", since)))
+        })
+        .transpose()?;
+    let direction = match query.get("propagation").map(String::as_str) {
+        None => None,
+        Some("forward") => Some(PropagationType::Forward),
+        Some("backward") => Some(PropagationType::Backward),
+        Some(other) => {
+            return Err(ServerError::InvalidInput(format!(
+                "Invalid propagation '{}'; expected forward or backward",
+                other
+            )))
+        }
+    };
+    let params = ListParams::parse::<SignalActivity>(&query, &["since", "propagation"])?;
+    let page = paginate(server.signal_activity(since, direction), &params)?;
+    Ok(Json(ApiResponse::success(page)))
+}
+
+async fn get_chain_result(
+    State(server): State<Arc<HAL9Server>>,
+    Path(chain_id): Path<String>,
+) -> Result<impl IntoResponse, ServerError> {
+    let result = server.get_chain_result(&chain_id).await?;
+    Ok(Json(ApiResponse::success(result)))
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct ReplayRequest {
+    /// Input to replay with instead of the original
+    content: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct ReplayResponse {
+    chain_id: String,
+    replay_of: String,
+    /// Comparison of the replay with its source
+    compare_url: String,
+}
+
+async fn replay_chain(
+    State(server): State<Arc<HAL9Server>>,
+    Path(chain_id): Path<String>,
+    user: Option<Extension<AuthUser>>,
+    req: Option<Json<ReplayRequest>>,
+) -> Result<impl IntoResponse, ServerError> {
+    let Json(req) = req.unwrap_or_default();
+    let owner = user.map(|Extension(user)| ChainOwner::from(&user));
+    let replay_id = server.replay_chain(owner.as_ref(), &chain_id, req.content).await?;
+    Ok(Json(ApiResponse::success(ReplayResponse {
+        compare_url: format!("/api/v1/chains/compare?a={}&b={}", chain_id, replay_id),
+        chain_id: replay_id,
+        replay_of: chain_id,
+    })))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CompareQuery {
+    pub a: String,
+    pub b: String,
+}
+
+async fn compare_chains(
+    State(server): State<Arc<HAL9Server>>,
+    Query(query): Query<CompareQuery>,
+) -> Result<impl IntoResponse, ServerError> {
+    Ok(Json(ApiResponse::success(server.compare_chains(&query.a, &query.b).await?)))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct VisualizationQuery {
+    #[serde(default)]
+    pub format: VisualizationFormat,
+}
+
+async fn visualize_chain(
+    State(server): State<Arc<HAL9Server>>,
+    Path(root_id): Path<String>,
+    Query(query): Query<VisualizationQuery>,
+) -> Result<impl IntoResponse, ServerError> {
+    let graph = server.chain_graph(&root_id).await?;
+    Ok((
+        [(axum::http::header::CONTENT_TYPE, query.format.content_type())],
+        graph.render(query.format),
+    ))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReceiptQuery {
+    pub signal_id: Option<String>,
+}
+
+async fn get_chain_receipt(
+    State(server): State<Arc<HAL9Server>>,
+    Path(root_id): Path<String>,
+    Query(query): Query<ReceiptQuery>,
+) -> Result<impl IntoResponse, ServerError> {
+    let receipt = server.chain_receipt(&root_id, query.signal_id.as_deref()).await?;
+    Ok(Json(ApiResponse::success(receipt)))
+}
+
+async fn list_neurons(
+    State(server): State<Arc<HAL9Server>>,
+    Query(query): Query<HashMap<String, String>>,
+) -> Result<impl IntoResponse, ServerError> {
+    let params = ListParams::parse::<NeuronInfo>(&query, &[])?;
+    let page = paginate(server.list_neurons().await?, &params)?;
+    Ok(Json(ApiResponse::success(page)))
+}
+
+async fn get_neuron(
+    State(server): State<Arc<HAL9Server>>,
+    Path(neuron_id): Path<String>,
+) -> Result<impl IntoResponse, ServerError> {
+    match server.get_neuron_info(&neuron_id).await {
+        Ok(info) => Ok(Json(ApiResponse::success(info))),
+        Err(e) => Ok(Json(ApiResponse::error(format!("Neuron not found: {}", e)))),
+    }
+}
+
+async fn get_neuron_health(
+    State(server): State<Arc<HAL9Server>>,
+    Path(neuron_id): Path<String>,
+) -> Result<impl IntoResponse, ServerError> {
+    match server.get_neuron_health(&neuron_id).await {
+        Ok(health) => Ok(Json(ApiResponse::success(health))),
+        Err(e) => Ok(Json(ApiResponse::error(format!("Failed to get health: {}", e)))),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct NeuronChangesQuery {
+    since_version: Option<u64>,
+}
+
+/// Neurons changed since `since_version`, or 304 without a body when none
+/// did
+async fn get_neuron_changes(
+    State(server): State<Arc<HAL9Server>>,
+    Query(query): Query<NeuronChangesQuery>,
+) -> Response {
+    let changes = server.neuron_changes(query.since_version).await;
+    if changes.is_empty() {
+        return StatusCode::NOT_MODIFIED.into_response();
+    }
+    Json(ApiResponse::success(changes)).into_response()
+}
+
+/// Neuron changes over WebSocket: what changed since `since_version`, or
+/// every neuron, then each change as the feed finds it
+async fn neuron_changes_websocket(
+    ws: axum::extract::ws::WebSocketUpgrade,
+    State(server): State<Arc<HAL9Server>>,
+    Query(query): Query<NeuronChangesQuery>,
+) -> impl IntoResponse {
+    ws.on_upgrade(move |socket| stream_neuron_changes(socket, server, query.since_version))
+}
+
+async fn stream_neuron_changes(
+    mut socket: axum::extract::ws::WebSocket,
+    server: Arc<HAL9Server>,
+    since: Option<u64>,
+) {
+    use axum::extract::ws::Message;
+    
+    let registry = server.registry();
+    let mut versions = registry.changes().subscribe();
+    let mut changes = registry.changes_since(since).await;
+    loop {
+        if !changes.is_empty() {
+            let Ok(json) = serde_json::to_string(&changes) else { break };
+            if socket.send(Message::Text(json)).await.is_err() {
+                break;
+            }
+        }
+        
+        tokio::select! {
+            msg = socket.recv() => match msg {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => {}
+            },
+            changed = versions.changed() => if changed.is_err() {
+                break;
+            },
+        }
+        // Versions skipped while sending are covered by asking from the
+        // last one sent
+        changes = registry.changes().since(Some(changes.version));
+    }
+}
+
+async fn get_metrics(
+    State(server): State<Arc<HAL9Server>>,
+) -> Result<impl IntoResponse, ServerError> {
+    let metrics = server.get_metrics().await?;
+    Ok(Json(ApiResponse::success(metrics)))
+}
+
+async fn get_slo_status(
+    State(server): State<Arc<HAL9Server>>,
+) -> Result<impl IntoResponse, ServerError> {
+    Ok(Json(ApiResponse::success(server.slo_status())))
+}
+
+async fn get_plugin_stats(
+    State(server): State<Arc<HAL9Server>>,
+    Path(name): Path<String>,
+) -> Result<impl IntoResponse, ServerError> {
+    let stats = server.metrics().plugin_stats(&name)
+        .ok_or_else(|| ServerError::NotFound(format!("No calls recorded for plugin {}", name)))?;
+    Ok(Json(ApiResponse::success(stats)))
+}
+
+async fn export_metrics(
+    State(server): State<Arc<HAL9Server>>,
+    Query(params): Query<std::collections::HashMap<String, String>>,
+) -> Result<impl IntoResponse, ServerError> {
+    let format = params.get("format").map(|s| s.as_str()).unwrap_or("json");
+    
+    match format {
+        "json" => {
+            let metrics = server.get_metrics().await?;
+            Ok(Json(metrics).into_response())
+        }
+        "prometheus" => {
+            let prometheus_data = crate::prometheus_exporter::export_metrics(server).await;
+            Ok((StatusCode::OK, prometheus_data).into_response())
+        }
+        _ => Ok(Json(ApiResponse::<String>::error("Unsupported format")).into_response()),
+    }
+}
+
+async fn prometheus_metrics(
+    State(server): State<Arc<HAL9Server>>,
+) -> Result<impl IntoResponse, ServerError> {
+    let prometheus_data = crate::prometheus_exporter::export_metrics(server).await;
+    Ok((
+        StatusCode::OK,
+        [(axum::http::header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        prometheus_data,
+    ))
+}
+
+async fn reload_network_tls(
+    State(server): State<Arc<HAL9Server>>,
+) -> Result<impl IntoResponse, ServerError> {
+    server.reload_network_tls().await?;
+    Ok(Json(ApiResponse::success(serde_json::json!({ "reloaded": true }))))
+}
+
+async fn get_network_status(
+    State(server): State<Arc<HAL9Server>>,
+) -> Result<impl IntoResponse, ServerError> {
+    match server.network_status().await {
+        Some(status) => Ok(Json(ApiResponse::success(status))),
+        None => {
+            let disabled_status = crate::server::NetworkStatus {
+                enabled: false,
+                server_id: String::new(),
+                connected_servers: vec![],
+                remote_neurons: 0,
+            };
+            Ok(Json(ApiResponse::success(disabled_status)))
+        }
+    }
+}
+
+async fn websocket_handler(
+    ws: axum::extract::ws::WebSocketUpgrade,
+    State(server): State<Arc<HAL9Server>>,
+    Query(filter): Query<StreamFilter>,
+) -> impl IntoResponse {
+    ws.on_upgrade(|socket| handle_websocket(socket, server, filter))
+}
+
+async fn handle_websocket(
+    mut socket: axum::extract::ws::WebSocket,
+    server: Arc<HAL9Server>,
+    filter: StreamFilter,
+) {
+    use axum::extract::ws::Message;
+    
+    // Subscribe to server events
+    let mut event_rx = server.subscribe_to_events().await;
+    
+    // Send initial connection message
+    let welcome_msg = WsMessage::ServerEvent {
+        event: "connected".to_string(),
+        details: "Connected to 2HAL9 server".to_string(),
+    };
+    
+    if let Ok(msg_json) = serde_json::to_string(&welcome_msg) {
+        let _ = socket.send(Message::Text(msg_json)).await;
+    }
+    
+    // Handle bidirectional communication
+    loop {
+        tokio::select! {
+            // Handle incoming messages from client
+            Some(msg) = socket.recv() => {
+                match msg {
+                    Ok(msg) => match msg {
+                    Message::Text(text) => {
+                        // Handle text messages if needed
+                        tracing::debug!("Received WebSocket message: {}", text);
+                    }
+                    Message::Close(_) => {
+                        tracing::info!("WebSocket client disconnected");
+                        break;
+                    }
+                    _ => {}
+                    },
+                    Err(_) => break,
+                }
+            }
+            
+            // Forward server events to client
+            Ok(event) = event_rx.recv() => {
+                if !filter.matches(&event) {
+                    continue;
+                }
+                if let Ok(msg_json) = serde_json::to_string(&event) {
+                    if socket.send(Message::Text(msg_json)).await.is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+    }
+}
+
+// Error handling
+impl IntoResponse for ServerError {
+    fn into_response(self) -> Response {
+        let (status, message) = match self {
             ServerError::NotFound(msg) => (StatusCode::NOT_FOUND, msg),
             ServerError::InvalidInput(msg) => (StatusCode::BAD_REQUEST, msg),
+            ServerError::LimitExceeded(msg) => (StatusCode::TOO_MANY_REQUESTS, msg),
+            ServerError::Forbidden(msg) | ServerError::ToolLimitExceeded(msg) => (StatusCode::FORBIDDEN, msg),
+            ServerError::Draining => (StatusCode::SERVICE_UNAVAILABLE, self.to_string()),
             ServerError::Internal(msg) => (StatusCode::INTERNAL_SERVER_ERROR, msg),
             _ => (StatusCode::INTERNAL_SERVER_ERROR, self.to_string()),
         };
@@ -516,14 +1816,11 @@ impl IntoResponse for ServerError {
 
 async fn get_recent_errors(
     axum::Extension(error_store): axum::Extension<Arc<ErrorStore>>,
-    Query(params): Query<HashMap<String, String>>,
+    Query(query): Query<HashMap<String, String>>,
 ) -> Result<impl IntoResponse, ServerError> {
-    let limit = params.get("limit")
-        .and_then(|s| s.parse::<usize>().ok())
-        .unwrap_or(50);
-    
-    let errors = error_store.get_recent_errors(limit).await;
-    Ok(Json(ApiResponse::success(errors)))
+    let params = ListParams::parse::<ErrorContext>(&query, &[])?;
+    let page = paginate(error_store.all_errors().await, &params)?;
+    Ok(Json(ApiResponse::success(page)))
 }
 
 async fn get_error_details(
@@ -536,8 +1833,450 @@ async fn get_error_details(
     }
 }
 
+async fn query_logs(
+    Query(query): Query<LogQuery>,
+) -> Result<impl IntoResponse, ServerError> {
+    let index = logging::log_index()
+        .filter(|index| index.is_enabled())
+        .ok_or_else(|| ServerError::ConfigError("Log index is disabled".to_string()))?;
+    Ok(Json(ApiResponse::success(index.query(&query))))
+}
+
+async fn get_log_stats() -> Result<impl IntoResponse, ServerError> {
+    let index = logging::log_index()
+        .ok_or_else(|| ServerError::ConfigError("Log index is not initialized".to_string()))?;
+    Ok(Json(ApiResponse::success(index.stats())))
+}
+
+#[derive(Debug, Deserialize)]
+struct SetLogLevelRequest {
+    module: String,
+    level: String,
+}
+
+async fn get_log_levels() -> Result<impl IntoResponse, ServerError> {
+    let levels = logging::log_levels()
+        .ok_or_else(|| ServerError::ConfigError("Log levels are not adjustable".to_string()))?;
+    Ok(Json(ApiResponse::success(levels.levels())))
+}
+
+async fn set_log_level(
+    Json(req): Json<SetLogLevelRequest>,
+) -> Result<impl IntoResponse, ServerError> {
+    let levels = logging::log_levels()
+        .ok_or_else(|| ServerError::ConfigError("Log levels are not adjustable".to_string()))?;
+    levels.set_level(&req.module, &req.level)
+        .map_err(ServerError::InvalidInput)?;
+    Ok(Json(ApiResponse::success(levels.levels())))
+}
+
+async fn clear_log_level(
+    Path(module): Path<String>,
+) -> Result<impl IntoResponse, ServerError> {
+    let levels = logging::log_levels()
+        .ok_or_else(|| ServerError::ConfigError("Log levels are not adjustable".to_string()))?;
+    if !levels.clear_level(&module).map_err(ServerError::Internal)? {
+        return Err(ServerError::NotFound(format!("No log level override for {}", module)));
+    }
+    Ok(Json(ApiResponse::success(levels.levels())))
+}
+
+#[derive(Debug, Deserialize)]
+struct ConfigOverrideRequest {
+    /// Dotted path, e.g. `claude.cost_controls.max_cost_per_day`
+    path: String,
+    value: serde_json::Value,
+}
+
+async fn get_effective_config(
+    State(server): State<Arc<HAL9Server>>,
+) -> Result<impl IntoResponse, ServerError> {
+    Ok(Json(ApiResponse::success(server.effective_config())))
+}
+
+async fn set_config_override(
+    State(server): State<Arc<HAL9Server>>,
+    Json(req): Json<ConfigOverrideRequest>,
+) -> Result<impl IntoResponse, ServerError> {
+    server.set_config_override(&req.path, req.value)?;
+    Ok(Json(ApiResponse::success(config_values_under(&server, &req.path))))
+}
+
+async fn clear_config_override(
+    State(server): State<Arc<HAL9Server>>,
+    Path(path): Path<String>,
+) -> Result<impl IntoResponse, ServerError> {
+    server.clear_config_override(&path)?;
+    Ok(Json(ApiResponse::success(config_values_under(&server, &path))))
+}
+
+/// Effective values at `path` and beneath it
+fn config_values_under(server: &HAL9Server, path: &str) -> Vec<hal9_core::config_layers::EffectiveValue> {
+    let prefix = format!("{}.", path);
+    server.effective_config()
+        .into_iter()
+        .filter(|value| value.path == path || value.path.starts_with(&prefix))
+        .collect()
+}
+
+async fn get_warm_pools(
+    State(server): State<Arc<HAL9Server>>,
+) -> Result<impl IntoResponse, ServerError> {
+    Ok(Json(ApiResponse::success(server.warm_pools())))
+}
+
+#[derive(Debug, Deserialize)]
+struct WarmPoolSizeRequest {
+    size: usize,
+}
+
+async fn set_warm_pool_size(
+    State(server): State<Arc<HAL9Server>>,
+    Path(neuron_type): Path<String>,
+    Json(req): Json<WarmPoolSizeRequest>,
+) -> Result<impl IntoResponse, ServerError> {
+    server.set_warm_pool_size(&neuron_type, req.size)?;
+    Ok(Json(ApiResponse::success(server.warm_pools())))
+}
+
+async fn get_topology(
+    State(server): State<Arc<HAL9Server>>,
+) -> Result<impl IntoResponse, ServerError> {
+    Ok(Json(ApiResponse::success(server.topology())))
+}
+
+/// Either operations to propose, or the token of a proposed patch to apply
+#[derive(Debug, Deserialize)]
+struct TopologyPatchRequest {
+    #[serde(default)]
+    ops: Vec<crate::topology::PatchOp>,
+    token: Option<String>,
+}
+
+async fn patch_topology(
+    State(server): State<Arc<HAL9Server>>,
+    user: Option<Extension<AuthUser>>,
+    Json(req): Json<TopologyPatchRequest>,
+) -> Result<Response, ServerError> {
+    match (req.token, req.ops.is_empty()) {
+        (None, false) => Ok(Json(ApiResponse::success(server.propose_topology_patch(req.ops)?)).into_response()),
+        (Some(token), true) => {
+            let actor = user.map(|Extension(user)| user.username);
+            let applied = server.apply_topology_patch(&token, actor.as_deref()).await?;
+            Ok(Json(ApiResponse::success(applied)).into_response())
+        }
+        _ => Err(ServerError::InvalidInput(
+            "Send either ops to propose a topology patch or the token of a proposed patch".to_string(),
+        )),
+    }
+}
+
+async fn revert_topology(
+    State(server): State<Arc<HAL9Server>>,
+    user: Option<Extension<AuthUser>>,
+) -> Result<impl IntoResponse, ServerError> {
+    let actor = user.map(|Extension(user)| user.username);
+    Ok(Json(ApiResponse::success(server.revert_topology_patch(actor.as_deref()).await?)))
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct PauseLayerRequest {
+    reason: Option<String>,
+    /// Resume automatically after this many seconds
+    duration_secs: Option<u64>,
+}
+
+async fn get_paused_layers(
+    State(server): State<Arc<HAL9Server>>,
+) -> Result<impl IntoResponse, ServerError> {
+    Ok(Json(ApiResponse::success(server.paused_layers())))
+}
+
+async fn pause_layer(
+    State(server): State<Arc<HAL9Server>>,
+    Path(layer): Path<String>,
+    user: Option<Extension<AuthUser>>,
+    req: Option<Json<PauseLayerRequest>>,
+) -> Result<impl IntoResponse, ServerError> {
+    let Json(req) = req.unwrap_or_default();
+    let actor = user.map(|Extension(user)| user.username);
+    let pause = server.pause_layer(
+        &layer,
+        req.reason,
+        req.duration_secs.map(std::time::Duration::from_secs),
+        actor.as_deref(),
+    )?;
+    Ok(Json(ApiResponse::success(pause)))
+}
+
+async fn resume_layer(
+    State(server): State<Arc<HAL9Server>>,
+    Path(layer): Path<String>,
+    user: Option<Extension<AuthUser>>,
+) -> Result<impl IntoResponse, ServerError> {
+    let actor = user.map(|Extension(user)| user.username);
+    let released = server.resume_layer(&layer, actor.as_deref()).await?;
+    Ok(Json(ApiResponse::success(serde_json::json!({
+        "layer": parse_layer(&layer),
+        "released": released,
+    }))))
+}
+
+async fn get_degraded(
+    State(server): State<Arc<HAL9Server>>,
+) -> Result<impl IntoResponse, ServerError> {
+    Ok(Json(ApiResponse::success(server.degraded_status())))
+}
+
+async fn enter_degraded(
+    State(server): State<Arc<HAL9Server>>,
+    user: Option<Extension<AuthUser>>,
+) -> Result<impl IntoResponse, ServerError> {
+    let actor = user.map(|Extension(user)| user.username);
+    Ok(Json(ApiResponse::success(server.enter_degraded(actor.as_deref()))))
+}
+
+async fn exit_degraded(
+    State(server): State<Arc<HAL9Server>>,
+    user: Option<Extension<AuthUser>>,
+) -> Result<impl IntoResponse, ServerError> {
+    let actor = user.map(|Extension(user)| user.username);
+    let released = server.exit_degraded(actor.as_deref()).await?;
+    Ok(Json(ApiResponse::success(serde_json::json!({
+        "released": released,
+    }))))
+}
+
+async fn receive_ingest_webhook(
+    State(server): State<Arc<HAL9Server>>,
+    Path(source): Path<String>,
+    headers: HeaderMap,
+    body: axum::body::Bytes,
+) -> Result<Response, ServerError> {
+    let ingestion = server.ingestion().await?;
+    ingestion.verify_webhook(&source, &headers, &body)?;
+    let ingested = ingestion.ingest(&source, &body).await?;
+    Ok(ingestion::webhook::response(ingested))
+}
+
+async fn get_ingestion_status(
+    State(server): State<Arc<HAL9Server>>,
+) -> Result<impl IntoResponse, ServerError> {
+    Ok(Json(ApiResponse::success(server.ingestion_status().await)))
+}
+
+async fn get_quarantined_messages(
+    State(server): State<Arc<HAL9Server>>,
+    Query(query): Query<HashMap<String, String>>,
+) -> Result<impl IntoResponse, ServerError> {
+    let params = ListParams::parse::<QuarantinedMessage>(&query, &[])?;
+    let messages = server.quarantined_messages(query.get("source").map(String::as_str)).await?;
+    Ok(Json(ApiResponse::success(paginate(messages, &params)?)))
+}
+
+async fn pause_ingestion(
+    State(server): State<Arc<HAL9Server>>,
+    Path(source): Path<String>,
+    user: Option<Extension<AuthUser>>,
+) -> Result<impl IntoResponse, ServerError> {
+    let actor = user.map(|Extension(user)| user.username);
+    Ok(Json(ApiResponse::success(server.pause_ingestion(&source, actor.as_deref()).await?)))
+}
+
+async fn resume_ingestion(
+    State(server): State<Arc<HAL9Server>>,
+    Path(source): Path<String>,
+    user: Option<Extension<AuthUser>>,
+) -> Result<impl IntoResponse, ServerError> {
+    let actor = user.map(|Extension(user)| user.username);
+    Ok(Json(ApiResponse::success(server.resume_ingestion(&source, actor.as_deref()).await?)))
+}
+
+async fn list_quality_benchmarks(
+    State(server): State<Arc<HAL9Server>>,
+) -> Result<impl IntoResponse, ServerError> {
+    Ok(Json(ApiResponse::success(server.quality_benchmarks().await?)))
+}
+
+async fn create_quality_benchmark(
+    State(server): State<Arc<HAL9Server>>,
+    Json(benchmark): Json<NewBenchmark>,
+) -> Result<impl IntoResponse, ServerError> {
+    let benchmark = server.add_quality_benchmark(benchmark).await?;
+    Ok((StatusCode::CREATED, Json(ApiResponse::success(benchmark))))
+}
+
+async fn delete_quality_benchmark(
+    State(server): State<Arc<HAL9Server>>,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, ServerError> {
+    server.delete_quality_benchmark(&id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn run_quality_benchmarks(
+    State(server): State<Arc<HAL9Server>>,
+) -> Result<impl IntoResponse, ServerError> {
+    Ok(Json(ApiResponse::success(server.run_quality_benchmarks().await?)))
+}
+
+async fn list_quality_runs(
+    State(server): State<Arc<HAL9Server>>,
+    Query(query): Query<HashMap<String, String>>,
+) -> Result<impl IntoResponse, ServerError> {
+    let params = ListParams::parse::<QualityRun>(&query, &[])?;
+    Ok(Json(ApiResponse::success(paginate(server.quality_runs().await?, &params)?)))
+}
+
+async fn get_quality_run(
+    State(server): State<Arc<HAL9Server>>,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, ServerError> {
+    Ok(Json(ApiResponse::success(server.quality_run(&id).await?)))
+}
+
+async fn get_quality_trends(
+    State(server): State<Arc<HAL9Server>>,
+) -> Result<impl IntoResponse, ServerError> {
+    Ok(Json(ApiResponse::success(server.quality_trends().await?)))
+}
+
+async fn list_templates(
+    State(server): State<Arc<HAL9Server>>,
+) -> Result<impl IntoResponse, ServerError> {
+    Ok(Json(ApiResponse::success(server.chain_templates().await?)))
+}
+
+async fn create_template(
+    State(server): State<Arc<HAL9Server>>,
+    user: Option<Extension<AuthUser>>,
+    Json(template): Json<NewTemplate>,
+) -> Result<impl IntoResponse, ServerError> {
+    let user = user.map(|Extension(user)| user);
+    let template = server.add_template(user.as_ref(), template).await?;
+    Ok((StatusCode::CREATED, Json(ApiResponse::success(template))))
+}
+
+#[derive(Debug, Deserialize)]
+struct TemplateQuery {
+    /// Version to show instead of the latest
+    version: Option<u32>,
+}
+
+async fn get_template(
+    State(server): State<Arc<HAL9Server>>,
+    Path(name): Path<String>,
+    Query(query): Query<TemplateQuery>,
+) -> Result<impl IntoResponse, ServerError> {
+    Ok(Json(ApiResponse::success(server.chain_template(&name, query.version).await?)))
+}
+
+async fn list_template_versions(
+    State(server): State<Arc<HAL9Server>>,
+    Path(name): Path<String>,
+) -> Result<impl IntoResponse, ServerError> {
+    Ok(Json(ApiResponse::success(server.chain_template_versions(&name).await?)))
+}
+
+async fn submit_template(
+    State(server): State<Arc<HAL9Server>>,
+    Extension(idempotency): Extension<Arc<IdempotencyCache>>,
+    user: Option<Extension<AuthUser>>,
+    Path(name): Path<String>,
+    headers: HeaderMap,
+    Json(req): Json<SubmitTemplateRequest>,
+) -> Result<impl IntoResponse, ServerError> {
+    let user = user.map(|Extension(user)| user);
+    let owner = user.as_ref().map(ChainOwner::from);
+    
+    // A retry of a submission that already went through gets the same answer
+    let key = IdempotencyCache::key_for(&headers, &format!("templates/{}", name), owner.as_ref());
+    if let Some(response) = key.as_deref().and_then(|key| idempotency.get::<SubmitTemplateResponse>(key)) {
+        return Ok(Json(ApiResponse::success(response)));
+    }
+    
+    let (template, rendered) = server.render_template(user.as_ref(), &name, req).await?;
+    let signal = build_signal(rendered.request).map_err(ServerError::InvalidInput)?;
+    let signal_id = server.submit_signal_as(owner.as_ref(), signal).await?;
+    let response = SubmitTemplateResponse {
+        chain_id: signal_id.clone(),
+        signal_id,
+        template: template.name,
+        version: template.version,
+        artifacts: rendered.artifacts,
+    };
+    if let Some(key) = key {
+        idempotency.insert(key, &response);
+    }
+    Ok(Json(ApiResponse::success(response)))
+}
+
+async fn get_artifact(
+    State(server): State<Arc<HAL9Server>>,
+    user: Option<Extension<AuthUser>>,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, ServerError> {
+    Ok(Json(ApiResponse::success(server.template_artifact(user.as_deref(), &id).await?)))
+}
+
+async fn create_session(
+    State(server): State<Arc<HAL9Server>>,
+    user: Option<Extension<AuthUser>>,
+) -> Result<impl IntoResponse, ServerError> {
+    let owner = user.map(|Extension(user)| ChainOwner::from(&user));
+    let session = server.create_session(owner.as_ref()).await?;
+    Ok((StatusCode::CREATED, Json(ApiResponse::success(session))))
+}
+
+async fn list_sessions(
+    State(server): State<Arc<HAL9Server>>,
+    user: Option<Extension<AuthUser>>,
+) -> Result<impl IntoResponse, ServerError> {
+    let owner = user.map(|Extension(user)| ChainOwner::from(&user));
+    Ok(Json(ApiResponse::success(server.list_sessions(owner.as_ref()).await?)))
+}
+
+async fn get_session(
+    State(server): State<Arc<HAL9Server>>,
+    user: Option<Extension<AuthUser>>,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, ServerError> {
+    let owner = user.map(|Extension(user)| ChainOwner::from(&user));
+    Ok(Json(ApiResponse::success(server.session_view(owner.as_ref(), &id).await?)))
+}
+
+async fn reset_session(
+    State(server): State<Arc<HAL9Server>>,
+    user: Option<Extension<AuthUser>>,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, ServerError> {
+    let owner = user.map(|Extension(user)| ChainOwner::from(&user));
+    Ok(Json(ApiResponse::success(server.reset_session(owner.as_ref(), &id).await?)))
+}
+
+async fn delete_session(
+    State(server): State<Arc<HAL9Server>>,
+    user: Option<Extension<AuthUser>>,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, ServerError> {
+    let owner = user.map(|Extension(user)| ChainOwner::from(&user));
+    server.delete_session(owner.as_ref(), &id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
 // Helper functions
 
+/// Parse a user-supplied layer name into its canonical form
+pub(crate) fn parse_layer(layer: &str) -> Option<&'static str> {
+    match layer.to_lowercase().as_str() {
+        "l4" | "strategic" => Some("L4"),
+        "l3" | "design" => Some("L3"),
+        "l2" | "implementation" => Some("L2"),
+        "l1" | "execution" => Some("L1"),
+        _ => None,
+    }
+}
+
 fn calculate_average_latency(metrics: &crate::metrics::MetricsSnapshot) -> f64 {
     if metrics.layer_latencies.is_empty() {
         return 0.0;
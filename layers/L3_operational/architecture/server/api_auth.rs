@@ -5,16 +5,18 @@ use axum::{
     response::IntoResponse,
     http::StatusCode,
 };
-use serde::{Deserialize, Serialize};
+use serde::Serialize;
 use std::sync::Arc;
 use hal9_core::auth::{
     User, UserManager, CreateUserRequest, UpdateUserRequest,
     JwtManager, TokenPair,
-    ApiKeyManager, CreateApiKeyRequest, ApiKeyResponse, ApiKeyInfo,
-    AuthError,
+    ApiKeyManager, CreateApiKeyRequest, RotateApiKeyRequest, DEFAULT_ROTATION_OVERLAP_MINUTES, ApiKeyResponse, ApiKeyInfo,
+    AuthError, Permission,
 };
 use crate::auth_middleware::AuthUser;
 
+pub use hal9_api_types::{LoginRequest, LoginResponse, RefreshRequest, RefreshResponse, UserResponse};
+
 /// Authentication API state
 pub struct AuthApiState {
     pub user_manager: Arc<UserManager>,
@@ -22,41 +24,24 @@ pub struct AuthApiState {
     pub api_key_manager: Arc<ApiKeyManager>,
 }
 
-/// Login request
-#[derive(Debug, Deserialize)]
-pub struct LoginRequest {
-    pub username: String,
-    pub password: String,
-}
-
-/// Login response
-#[derive(Debug, Serialize)]
-pub struct LoginResponse {
-    pub user: UserResponse,
-    pub tokens: TokenPair,
-}
-
-/// User response (without sensitive data)
-#[derive(Debug, Serialize)]
-pub struct UserResponse {
-    pub id: String,
-    pub username: String,
-    pub email: String,
-    pub role: String,
-    pub created_at: i64,
-    pub is_active: bool,
+/// User without sensitive data
+fn user_response(user: User) -> UserResponse {
+    UserResponse {
+        id: user.id,
+        username: user.username,
+        email: user.email,
+        role: user.role,
+        created_at: user.created_at,
+        is_active: user.is_active,
+    }
 }
 
-impl From<User> for UserResponse {
-    fn from(user: User) -> Self {
-        Self {
-            id: user.id,
-            username: user.username,
-            email: user.email,
-            role: user.role,
-            created_at: user.created_at,
-            is_active: user.is_active,
-        }
+/// Tokens as sent over the wire
+fn wire_tokens(tokens: TokenPair) -> hal9_api_types::TokenPair {
+    hal9_api_types::TokenPair {
+        access_token: tokens.access_token,
+        refresh_token: tokens.refresh_token,
+        expires_in: tokens.expires_in,
     }
 }
 
@@ -66,7 +51,7 @@ pub async fn register(
     Json(request): Json<CreateUserRequest>,
 ) -> Result<(StatusCode, Json<UserResponse>), AuthErrorResponse> {
     let user = state.user_manager.create_user(request).await?;
-    Ok((StatusCode::CREATED, Json(user.into())))
+    Ok((StatusCode::CREATED, Json(user_response(user))))
 }
 
 /// Login user
@@ -82,23 +67,12 @@ pub async fn login(
         .generate_token_pair(&user.id, &user.username, &user.role)?;
     
     Ok(Json(LoginResponse {
-        user: user.into(),
-        tokens,
+        user: user_response(user),
+        tokens: wire_tokens(tokens),
     }))
 }
 
 /// Refresh token
-#[derive(Debug, Deserialize)]
-pub struct RefreshRequest {
-    pub refresh_token: String,
-}
-
-#[derive(Debug, Serialize)]
-pub struct RefreshResponse {
-    pub access_token: String,
-    pub expires_in: i64,
-}
-
 pub async fn refresh_token(
     State(state): State<Arc<AuthApiState>>,
     Json(request): Json<RefreshRequest>,
@@ -118,7 +92,7 @@ pub async fn get_profile(
     State(state): State<Arc<AuthApiState>>,
 ) -> Result<Json<UserResponse>, AuthErrorResponse> {
     let user_data = state.user_manager.get_user(&user.user_id).await?;
-    Ok(Json(user_data.into()))
+    Ok(Json(user_response(user_data)))
 }
 
 /// Update user profile
@@ -130,15 +104,33 @@ pub async fn update_profile(
     let updated_user = state.user_manager
         .update_user(&user.user_id, request)
         .await?;
-    Ok(Json(updated_user.into()))
+    Ok(Json(user_response(updated_user)))
 }
 
-/// Create API key
+/// Create API key. Scopes may not exceed the caller's own permissions, a
+/// caller bound to an organization can only create keys for it, and only a
+/// system admin may name an organization it is not bound to.
 pub async fn create_api_key(
     Extension(user): Extension<AuthUser>,
     State(state): State<Arc<AuthApiState>>,
-    Json(request): Json<CreateApiKeyRequest>,
+    Json(mut request): Json<CreateApiKeyRequest>,
 ) -> Result<(StatusCode, Json<ApiKeyResponse>), AuthErrorResponse> {
+    if !user.permissions.contains_all(&request.permissions) {
+        return Err(AuthError::InsufficientPermissions.into());
+    }
+    match (&user.org_id, &request.org_id) {
+        (Some(own), Some(requested)) if own != requested => {
+            return Err(AuthError::InsufficientPermissions.into());
+        }
+        (Some(own), None) => request.org_id = Some(own.clone()),
+        // Nothing records who belongs to an organization, so only a system
+        // admin may bind a key to one it is not in
+        (None, Some(_)) if !user.permissions.has(&Permission::SystemAdmin) => {
+            return Err(AuthError::InsufficientPermissions.into());
+        }
+        _ => {}
+    }
+    
     let api_key = state.api_key_manager
         .create_api_key(&user.user_id, request)
         .await?;
@@ -161,6 +153,34 @@ pub async fn list_api_keys(
     Ok(Json(key_infos))
 }
 
+/// Get one API key's metadata and usage
+pub async fn get_api_key(
+    Extension(user): Extension<AuthUser>,
+    State(state): State<Arc<AuthApiState>>,
+    Path(key_id): Path<String>,
+) -> Result<Json<ApiKeyInfo>, AuthErrorResponse> {
+    let key = state.api_key_manager
+        .get_api_key(&user.user_id, &key_id)
+        .await?;
+    Ok(Json(key.into()))
+}
+
+/// Rotate API key, keeping the old secret valid for the overlap window
+pub async fn rotate_api_key(
+    Extension(user): Extension<AuthUser>,
+    State(state): State<Arc<AuthApiState>>,
+    Path(key_id): Path<String>,
+    request: Option<Json<RotateApiKeyRequest>>,
+) -> Result<Json<ApiKeyResponse>, AuthErrorResponse> {
+    let overlap_minutes = request
+        .and_then(|Json(request)| request.overlap_minutes)
+        .unwrap_or(DEFAULT_ROTATION_OVERLAP_MINUTES);
+    let api_key = state.api_key_manager
+        .rotate_api_key(&user.user_id, &key_id, chrono::Duration::minutes(overlap_minutes))
+        .await?;
+    Ok(Json(api_key))
+}
+
 /// Revoke API key
 pub async fn revoke_api_key(
     Extension(user): Extension<AuthUser>,
@@ -200,6 +220,9 @@ impl IntoResponse for AuthErrorResponse {
             AuthError::InsufficientPermissions => (StatusCode::FORBIDDEN, "Insufficient permissions"),
             AuthError::ApiKeyNotFound => (StatusCode::NOT_FOUND, "API key not found"),
             AuthError::ApiKeyExpired => (StatusCode::UNAUTHORIZED, "API key expired"),
+            AuthError::ApiKeyRevoked => (StatusCode::CONFLICT, "API key revoked"),
+            AuthError::RateLimitExceeded => (StatusCode::TOO_MANY_REQUESTS, "API key rate limit exceeded"),
+            AuthError::BudgetExhausted => (StatusCode::TOO_MANY_REQUESTS, "API key token budget exhausted"),
             AuthError::ValidationError(_) => (StatusCode::BAD_REQUEST, "Validation error"),
             _ => (StatusCode::INTERNAL_SERVER_ERROR, "Internal server error"),
         };
@@ -1,8 +1,13 @@
 //! Code Generation API endpoints
 
 use axum::{
-    extract::{State, Json, Path},
+    extract::{
+        ws::{close_code, CloseFrame, Message, WebSocket, WebSocketUpgrade},
+        State, Json, Path, Query,
+    },
     response::IntoResponse,
+    routing::get,
+    Router,
 };
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
@@ -12,6 +17,7 @@ use uuid::Uuid;
 use crate::{
     server::HAL9Server,
     error::ServerError,
+    codegen_jobs::{CodegenJob, CodegenJobs},
 };
 use hal9_core::NeuronSignal;
 
@@ -208,16 +214,22 @@ pub async fn generate_project(
     );
     signal.metadata = metadata;
     
+    let location = format!("/projects/{}", project_id);
+    let job = state.server.codegen_jobs().create(&project_id, &location);
+    let _ = job.stage("queued", 0);
+    
     match state.server.send_signal(signal).await {
         Ok(_) => {
+            let _ = job.stage("architecture", 5);
             Ok(Json(GenerateProjectResponse {
                 project_id: project_id.clone(),
                 status: "processing".to_string(),
                 message: "Project generation started".to_string(),
-                location: Some(format!("/projects/{}", project_id)),
+                location: Some(location),
             }))
         }
         Err(e) => {
+            let _ = job.fail(&e.to_string());
             Err(ServerError::Internal(format!("Failed to start generation: {}", e)))
         }
     }
@@ -387,19 +399,88 @@ pub async fn refactor_code(
     }))
 }
 
+/// Routes for following generation jobs, sharing the codegen router's auth
+pub fn job_routes(jobs: Arc<CodegenJobs>) -> Router {
+    Router::new()
+        .route("/api/v1/codegen/project/:id", get(get_project_status))
+        .route("/api/v1/codegen/jobs/:id/stream", get(stream_job))
+        .with_state(jobs)
+}
+
 /// Get project generation status
 pub async fn get_project_status(
-    State(_state): State<Arc<CodegenApiState>>,
+    State(jobs): State<Arc<CodegenJobs>>,
     Path(project_id): Path<String>,
 ) -> Result<impl IntoResponse, ServerError> {
-    // In production, this would check actual generation status
-    Ok(Json(serde_json::json!({
-        "project_id": project_id,
-        "status": "completed",
-        "progress": 100,
-        "files_generated": 25,
-        "location": format!("./generated/{}", project_id),
-    })))
+    let job = jobs.get(&project_id)
+        .ok_or_else(|| ServerError::NotFound(format!("Codegen job {} not found", project_id)))?;
+    Ok(Json(job.status()))
+}
+
+/// Query parameters for a job stream
+#[derive(Debug, Deserialize)]
+pub struct StreamQuery {
+    /// First sequence number to send; a reconnecting client passes one past
+    /// the last event it saw
+    pub from_seq: Option<u64>,
+}
+
+/// Stream a job's progress events over a WebSocket, closing after the
+/// terminal event
+pub async fn stream_job(
+    ws: WebSocketUpgrade,
+    State(jobs): State<Arc<CodegenJobs>>,
+    Path(job_id): Path<String>,
+    Query(query): Query<StreamQuery>,
+) -> Result<impl IntoResponse, ServerError> {
+    let job = jobs.get(&job_id)
+        .ok_or_else(|| ServerError::NotFound(format!("Codegen job {} not found", job_id)))?;
+    let from_seq = query.from_seq.unwrap_or(1).max(1);
+    let next_seq = job.status().last_seq + 1;
+    if from_seq > next_seq {
+        return Err(ServerError::InvalidInput(format!(
+            "from_seq {} is past the latest event of job {} ({})", from_seq, job_id, next_seq - 1
+        )));
+    }
+    
+    Ok(ws.on_upgrade(move |socket| stream_events(socket, job, from_seq)))
+}
+
+async fn stream_events(mut socket: WebSocket, job: Arc<CodegenJob>, mut next_seq: u64) {
+    let mut latest = job.subscribe();
+    
+    loop {
+        // Mark as seen before reading, so an event emitted meanwhile wakes us
+        latest.borrow_and_update();
+        for event in job.events_since(next_seq) {
+            next_seq = event.seq + 1;
+            let Ok(text) = serde_json::to_string(&event) else { continue };
+            if socket.send(Message::Text(text)).await.is_err() {
+                return;
+            }
+        }
+        
+        // Also reached by a client resuming after the terminal event
+        if job.is_finished() {
+            let _ = socket.send(Message::Close(Some(CloseFrame {
+                code: close_code::NORMAL,
+                reason: "job finished".into(),
+            }))).await;
+            return;
+        }
+        
+        tokio::select! {
+            changed = latest.changed() => {
+                if changed.is_err() {
+                    return;
+                }
+            }
+            msg = socket.recv() => match msg {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => return,
+                Some(Ok(_)) => {}
+            }
+        }
+    }
 }
 
 /// List available project templates
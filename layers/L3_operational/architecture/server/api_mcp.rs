@@ -0,0 +1,238 @@
+//! MCP endpoint serving HAL9 itself as tools
+//!
+//! Implements the streamable HTTP transport: clients POST JSON-RPC messages
+//! to `/mcp` and receive JSON responses. The server never initiates
+//! messages, so `GET /mcp` (the optional SSE stream) is not offered.
+
+use async_trait::async_trait;
+use axum::{
+    body::Bytes,
+    extract::State,
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tracing::warn;
+
+use hal9_core::{
+    Error, Result, NeuronSignal,
+    auth::Permission,
+    mcp::{Hal9Backend, Hal9McpServer},
+    memory::{MemoryNamespace, MemorySearch},
+};
+use crate::auth_middleware::{api_key_user, AuthState, AuthUser};
+use crate::chain_limits::ChainOwner;
+use crate::error::ServerError;
+use crate::server::HAL9Server;
+
+/// Header carrying the MCP session ID
+pub const SESSION_HEADER: &str = "Mcp-Session-Id";
+
+/// Source of signals submitted over MCP, and the memory principal of
+/// callers without an identity
+const MCP_CLIENT: &str = "mcp-client";
+
+/// Layer MCP callers read memory from
+const MCP_LAYER: &str = "MCP";
+
+/// [`Hal9Backend`] implementation over a running server, acting for the
+/// caller when there is one
+pub struct ServerMcpBackend {
+    server: Arc<HAL9Server>,
+    caller: Option<AuthUser>,
+}
+
+impl ServerMcpBackend {
+    pub fn new(server: Arc<HAL9Server>) -> Self {
+        Self { server, caller: None }
+    }
+
+    /// Backend whose tools run with the permissions of `caller`
+    pub fn for_caller(server: Arc<HAL9Server>, caller: AuthUser) -> Self {
+        Self { server, caller: Some(caller) }
+    }
+
+    /// Refuse a tool needing `permission` the caller lacks
+    fn authorize(&self, permission: Permission) -> Result<()> {
+        match &self.caller {
+            Some(caller) if !caller.permissions.has(&permission) => Err(Error::PermissionDenied(format!(
+                "This tool needs the {:?} permission",
+                permission
+            ))),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[async_trait]
+impl Hal9Backend for ServerMcpBackend {
+    async fn submit_signal(&self, layer: &str, content: &str) -> Result<String> {
+        self.authorize(Permission::SendSignal)?;
+        let layer = crate::api::parse_layer(layer)
+            .ok_or_else(|| Error::InvalidInput(format!("Invalid layer: {}", layer)))?;
+
+        let signal = NeuronSignal::forward(
+            MCP_CLIENT,
+            &format!("neuron-{}", layer.to_lowercase()),
+            "MCP",
+            layer,
+            content.to_string(),
+        );
+
+        // Charged to and limited for the caller, as with POST /api/v1/signals
+        let owner = self.caller.as_ref().map(ChainOwner::from);
+        self.server.submit_signal_as(owner.as_ref(), signal).await
+            .map_err(|e| Error::Routing(e.to_string()))
+    }
+
+    async fn get_chain_result(&self, chain_id: &str) -> Result<Value> {
+        self.authorize(Permission::ViewSignals)?;
+        let result = self.server.get_chain_result(chain_id).await
+            .map_err(|e| Error::NotFound(e.to_string()))?;
+        Ok(serde_json::to_value(result)?)
+    }
+
+    async fn list_neurons(&self) -> Result<Value> {
+        self.authorize(Permission::ViewNeuron)?;
+        let neurons = self.server.list_neurons().await
+            .map_err(|e| Error::Other(anyhow::anyhow!(e.to_string())))?;
+        Ok(serde_json::to_value(neurons)?)
+    }
+
+    async fn search_memory(
+        &self,
+        query: &str,
+        namespace: Option<&str>,
+        neuron_id: Option<&str>,
+        limit: usize,
+    ) -> Result<Value> {
+        self.authorize(Permission::ViewMemory)?;
+        let namespace = match namespace {
+            Some(namespace) => namespace.parse()?,
+            None => MemoryNamespace::Global,
+        };
+        let search = MemorySearch {
+            neuron_id: neuron_id.map(|s| s.to_string()),
+            content_query: Some(query.to_string()),
+            limit,
+            ..Default::default()
+        };
+
+        // The caller reads as itself, so the namespace policy and its grants apply
+        let principal = self.caller.as_ref().map_or(MCP_CLIENT, |caller| caller.user_id.as_str());
+        let entries = self.server.read_memory(principal, MCP_LAYER, &namespace, search).await
+            .map_err(|e| match e {
+                ServerError::Forbidden(reason) => Error::PermissionDenied(reason),
+                e => Error::Storage(e.to_string()),
+            })?;
+
+        Ok(Value::Array(entries.into_iter().map(|entry| json!({
+            "id": entry.id,
+            "neuron_id": entry.neuron_id,
+            "layer": entry.layer,
+            "type": entry.entry_type,
+            "content": entry.content,
+            "importance": entry.importance,
+            "timestamp": entry.timestamp,
+        })).collect()))
+    }
+}
+
+/// MCP HTTP state
+pub struct McpState {
+    pub server: Arc<HAL9Server>,
+    /// Server acting for no one in particular, as over stdio
+    pub mcp: Hal9McpServer,
+    auth_state: Option<AuthState>,
+}
+
+impl McpState {
+    pub fn new(server: Arc<HAL9Server>) -> Self {
+        let backend = Arc::new(ServerMcpBackend::new(server.clone()));
+        let auth_state = server.jwt_manager.clone()
+            .zip(server.api_key_manager.clone())
+            .zip(server.user_manager.clone())
+            .map(|((jwt_manager, api_key_manager), user_manager)| AuthState { jwt_manager, api_key_manager, user_manager });
+        Self {
+            server,
+            mcp: Hal9McpServer::new(backend),
+            auth_state,
+        }
+    }
+}
+
+/// Extract an API key from `X-API-Key` or a bearer `Authorization` header
+fn extract_api_key(headers: &HeaderMap) -> Option<String> {
+    headers.get("X-API-Key")
+        .and_then(|v| v.to_str().ok())
+        .map(|s| s.to_string())
+        .or_else(|| {
+            headers.get(header::AUTHORIZATION)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.strip_prefix("Bearer "))
+                .map(|s| s.to_string())
+        })
+}
+
+/// POST /mcp - handle a JSON-RPC message or batch
+pub async fn mcp_post(
+    State(state): State<Arc<McpState>>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    // Reuse API keys when authentication is enabled; tools then run with
+    // the key's scopes
+    let caller_mcp;
+    let mcp = match &state.auth_state {
+        Some(auth_state) => {
+            let caller = match extract_api_key(&headers) {
+                Some(key) => api_key_user(auth_state, &key).await.ok(),
+                None => None,
+            };
+            let Some((caller, _)) = caller else {
+                return (StatusCode::UNAUTHORIZED, Json(json!({
+                    "jsonrpc": "2.0",
+                    "id": null,
+                    "error": { "code": -32001, "message": "Unauthorized" }
+                }))).into_response();
+            };
+            caller_mcp = Hal9McpServer::new(Arc::new(ServerMcpBackend::for_caller(state.server.clone(), caller)));
+            &caller_mcp
+        }
+        None => &state.mcp,
+    };
+
+    let text = match std::str::from_utf8(&body) {
+        Ok(text) => text,
+        Err(_) => return StatusCode::BAD_REQUEST.into_response(),
+    };
+
+    let is_initialize = serde_json::from_str::<Value>(text)
+        .map(|v| v.get("method").and_then(|m| m.as_str()) == Some("initialize"))
+        .unwrap_or(false);
+
+    match mcp.handle_text(text).await {
+        Some(response) => {
+            let mut response = Json(response).into_response();
+            if is_initialize {
+                let session_id = uuid::Uuid::new_v4().to_string();
+                match HeaderValue::from_str(&session_id) {
+                    Ok(value) => {
+                        response.headers_mut().insert(SESSION_HEADER, value);
+                    }
+                    Err(e) => warn!("Invalid MCP session id: {}", e),
+                }
+            }
+            response
+        }
+        // Notifications and responses only
+        None => StatusCode::ACCEPTED.into_response(),
+    }
+}
+
+/// GET /mcp - server-initiated streams are not supported
+pub async fn mcp_get() -> impl IntoResponse {
+    StatusCode::METHOD_NOT_ALLOWED
+}
@@ -0,0 +1,85 @@
+//! Organization API
+//!
+//! Only reachable with authentication enabled. Callers need the
+//! `ViewOrgUsage` permission and must belong to the organization; system
+//! admins without an organization may read any.
+
+use axum::{
+    extract::{Extension, Path, Query, State},
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Deserialize;
+use std::sync::Arc;
+
+use crate::{
+    auth_middleware::AuthUser,
+    error::ServerError,
+    org_usage::parse_period,
+    report_privacy::budget_key,
+    server::HAL9Server,
+};
+use hal9_core::auth::Permission;
+
+/// Allow `user` to read `org_id`'s data
+pub fn authorize(user: &AuthUser, org_id: &str) -> Result<(), ServerError> {
+    let allowed = match &user.org_id {
+        Some(own) => own == org_id,
+        None => user.permissions.has(&Permission::SystemAdmin),
+    };
+    if allowed {
+        Ok(())
+    } else {
+        Err(ServerError::Forbidden(format!("Not an admin of organization {}", org_id)))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UsageQuery {
+    /// `day`, `week` or `month`
+    #[serde(default = "default_period")]
+    pub period: String,
+    /// `previous_period` to add deltas against the period before
+    pub compare_to: Option<String>,
+    /// `json`, the only format noised reports come in
+    pub format: Option<String>,
+    /// Epsilon of the differential privacy noise, charged to the caller's
+    /// privacy budget; `report_privacy.default_epsilon` if not given
+    pub dp_epsilon: Option<f64>,
+}
+
+fn default_period() -> String {
+    "month".to_string()
+}
+
+/// GET /api/v1/orgs/:id/usage
+pub async fn get_org_usage(
+    State(server): State<Arc<HAL9Server>>,
+    Extension(user): Extension<AuthUser>,
+    Path(org_id): Path<String>,
+    Query(query): Query<UsageQuery>,
+) -> Result<Response, ServerError> {
+    authorize(&user, &org_id)?;
+    let period = parse_period(&query.period).map_err(|e| ServerError::InvalidInput(e.to_string()))?;
+    let compare = match query.compare_to.as_deref() {
+        None => false,
+        Some("previous_period") => true,
+        Some(other) => {
+            return Err(ServerError::InvalidInput(format!(
+                "Unknown compare_to '{}': expected previous_period",
+                other
+            )))
+        }
+    };
+    if let Some(format) = query.format.as_deref().filter(|format| *format != "json") {
+        return Err(ServerError::InvalidInput(format!(
+            "Unknown format '{}': reports are released with privacy noise, as json only",
+            format
+        )));
+    }
+    let usage = server.org_usage(&org_id, period, compare).await?;
+    let privacy = server.report_privacy();
+    let epsilon = query.dp_epsilon.unwrap_or_else(|| privacy.default_epsilon());
+    let report = privacy.release(&budget_key(&user), epsilon, &usage).await?;
+    Ok(Json(report).into_response())
+}
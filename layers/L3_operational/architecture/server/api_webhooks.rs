@@ -0,0 +1,83 @@
+//! Webhook subscription API
+//!
+//! Webhooks belong to the caller's organization, or to the caller themselves
+//! if they have none. Without authentication every caller shares the
+//! `anonymous` owner.
+
+use axum::{
+    extract::{Extension, Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::{
+    auth_middleware::AuthUser,
+    error::ServerError,
+    pagination::{paginate, ListParams, Page},
+    server::HAL9Server,
+    webhooks::{CreateWebhookRequest, CreatedWebhook, Delivery, Webhook},
+};
+
+/// Owner key webhooks are registered under for a caller
+pub fn owner_key(user: Option<&AuthUser>) -> String {
+    user.map(|user| user.org_id.clone().unwrap_or_else(|| user.user_id.clone()))
+        .unwrap_or_else(|| "anonymous".to_string())
+}
+
+/// POST /api/v1/webhooks
+pub async fn create_webhook(
+    State(server): State<Arc<HAL9Server>>,
+    user: Option<Extension<AuthUser>>,
+    Json(req): Json<CreateWebhookRequest>,
+) -> Result<(StatusCode, Json<CreatedWebhook>), ServerError> {
+    let owner = owner_key(user.as_ref().map(|Extension(user)| user));
+    let created = server.webhooks().register(&owner, req).await?;
+    Ok((StatusCode::CREATED, Json(created)))
+}
+
+/// GET /api/v1/webhooks
+pub async fn list_webhooks(
+    State(server): State<Arc<HAL9Server>>,
+    user: Option<Extension<AuthUser>>,
+    Query(query): Query<HashMap<String, String>>,
+) -> Result<Json<Page<Webhook>>, ServerError> {
+    let owner = owner_key(user.as_ref().map(|Extension(user)| user));
+    let params = ListParams::parse::<Webhook>(&query, &[])?;
+    Ok(Json(paginate(server.webhooks().list(&owner), &params)?))
+}
+
+/// DELETE /api/v1/webhooks/:id
+pub async fn delete_webhook(
+    State(server): State<Arc<HAL9Server>>,
+    user: Option<Extension<AuthUser>>,
+    Path(id): Path<String>,
+) -> Result<StatusCode, ServerError> {
+    let owner = owner_key(user.as_ref().map(|Extension(user)| user));
+    server.webhooks().delete(&owner, &id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// GET /api/v1/webhooks/:id/deliveries
+pub async fn list_deliveries(
+    State(server): State<Arc<HAL9Server>>,
+    user: Option<Extension<AuthUser>>,
+    Path(id): Path<String>,
+    Query(query): Query<HashMap<String, String>>,
+) -> Result<Json<Page<Delivery>>, ServerError> {
+    let owner = owner_key(user.as_ref().map(|Extension(user)| user));
+    let params = ListParams::parse::<Delivery>(&query, &[])?;
+    Ok(Json(paginate(server.webhooks().deliveries(&owner, &id)?, &params)?))
+}
+
+/// POST /api/v1/webhooks/:id/test
+pub async fn test_webhook(
+    State(server): State<Arc<HAL9Server>>,
+    user: Option<Extension<AuthUser>>,
+    Path(id): Path<String>,
+) -> Result<Json<Delivery>, ServerError> {
+    let owner = owner_key(user.as_ref().map(|Extension(user)| user));
+    let delivery = server.webhooks().send_test(&owner, &id).await?;
+    Ok(Json(delivery))
+}
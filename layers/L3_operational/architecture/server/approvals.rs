@@ -0,0 +1,394 @@
+//! Approval gates inside signal chains
+//!
+//! Some outputs should not continue downstream before a person has looked at
+//! them, e.g. ones that make a browser automation neuron submit a form. A
+//! response to a forward signal is held here, together with the signals it
+//! forwards, when it carries a `REQUIRES_APPROVAL: <reason>` directive or
+//! comes from a neuron whose `settings.approval.required` is set. Its chain
+//! keeps running and lists the hold as a gate.
+//!
+//! Held outputs are announced to the chain owner's `approval.requested`
+//! webhooks and listed by `GET /api/v1/approvals`. Only system admins decide
+//! on them, and those in an organization only on its chains. Approving one
+//! records the step and sends its signals on, carrying the approver's edited
+//! content if they amended it; rejecting one fails that branch alone.
+//! Outputs nobody decides on within `approvals.timeout_secs` get
+//! `approvals.timeout_action`. Every hold and decision is audit-logged. Held
+//! outputs live in memory only.
+//!
+//! Neurons with `settings.approval.required` never start speculative
+//! children. Otherwise, on a speculative layer, a response stops starting
+//! them once its directive is read, and children it started before are
+//! cancelled, the gate holding its forwards instead.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+use hal9_core::config::{ApprovalConfig, ApprovalTimeoutAction};
+use hal9_core::{Error, NeuronConfig, NeuronSignal, PropagationType, Result};
+
+use crate::chain_tracker::{ChainTracker, GateStatus};
+use crate::error::{ServerError, ServerResult};
+use crate::webhooks::WebhookManager;
+
+/// Prefix of the response line asking for approval, followed by the reason
+pub const APPROVAL_DIRECTIVE: &str = "REQUIRES_APPROVAL:";
+
+/// Actor recorded when an approval times out
+pub const TIMEOUT_ACTOR: &str = "timeout";
+
+/// How often approval deadlines are checked
+const TIMEOUT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Longest an approval may wait for a decision
+const MAX_TIMEOUT_SECS: u64 = 30 * 24 * 3600;
+
+/// A neuron's `settings.approval`
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ApprovalSettings {
+    /// Hold every response of the neuron to a forward signal for approval
+    #[serde(default)]
+    pub required: bool,
+}
+
+impl ApprovalSettings {
+    /// The neuron's `settings.approval`, if it has any
+    pub fn for_neuron(neuron: &NeuronConfig) -> Result<Self> {
+        match neuron.settings.get("approval") {
+            Some(settings) => serde_json::from_value(settings.clone()).map_err(|e| {
+                Error::Config(format!("Invalid approval settings for neuron {}: {}", neuron.id, e))
+            }),
+            None => Ok(Self::default()),
+        }
+    }
+}
+
+/// The reason given on a response's `REQUIRES_APPROVAL:` line, if it has one
+pub fn requested(response: &str) -> Option<String> {
+    let line = response.lines().find(|l| l.starts_with(APPROVAL_DIRECTIVE))?;
+    let reason = line[APPROVAL_DIRECTIVE.len()..].trim();
+    Some(if reason.is_empty() { "Approval requested by the neuron" } else { reason }.to_string())
+}
+
+/// Check an approvals config before the server starts
+pub fn validate(config: &ApprovalConfig) -> Result<()> {
+    let invalid = |reason: &str| Err(Error::Config(format!("Invalid approvals config: {}", reason)));
+    if config.timeout_secs == 0 || config.timeout_secs > MAX_TIMEOUT_SECS {
+        return invalid("timeout_secs must be between 1 and 2592000 (30 days)");
+    }
+    if config.max_pending == 0 {
+        return invalid("max_pending must be at least 1");
+    }
+    Ok(())
+}
+
+/// An output waiting for a decision, as listed and sent to webhooks
+#[derive(Debug, Clone, Serialize)]
+pub struct PendingApproval {
+    pub id: String,
+    pub chain_id: Option<String>,
+    /// Organization owning the chain; only its admins may decide
+    pub org_id: Option<String>,
+    /// Signal whose response is held
+    pub signal_id: String,
+    pub neuron_id: String,
+    pub layer: String,
+    /// Why the output needs approval
+    pub reason: String,
+    /// The neuron's response
+    pub output: String,
+    /// Content the held forward signals carry, which an approver may edit
+    pub content: Option<String>,
+    /// Neurons the held signals are addressed to
+    pub forward_to: Vec<String>,
+    pub requested_at: DateTime<Utc>,
+    /// When `timeout_action` is taken if nobody decides first
+    pub expires_at: DateTime<Utc>,
+    pub timeout_action: ApprovalTimeoutAction,
+}
+
+/// How an approval was decided
+#[derive(Debug, Clone, Serialize)]
+pub struct ApprovalDecision {
+    pub id: String,
+    pub status: GateStatus,
+    pub decided_by: String,
+    /// The output continued with the approver's content
+    pub edited: bool,
+    /// Signals sent downstream
+    pub released: usize,
+}
+
+struct HeldOutput {
+    approval: PendingApproval,
+    signal: NeuronSignal,
+    children: Vec<NeuronSignal>,
+}
+
+/// Holds outputs until they are approved, rejected or time out
+pub struct ApprovalGates {
+    config: ApprovalConfig,
+    pending: Mutex<HashMap<String, HeldOutput>>,
+    /// Router queue approved signals are sent to
+    release_tx: Mutex<Option<mpsc::Sender<NeuronSignal>>>,
+    chain_tracker: Arc<ChainTracker>,
+    webhooks: Option<Arc<WebhookManager>>,
+}
+
+impl ApprovalGates {
+    pub fn new(config: ApprovalConfig, chain_tracker: Arc<ChainTracker>) -> Self {
+        Self {
+            config,
+            pending: Mutex::new(HashMap::new()),
+            release_tx: Mutex::new(None),
+            chain_tracker,
+            webhooks: None,
+        }
+    }
+
+    /// Announce held outputs to webhooks subscribed to `approval.requested`
+    pub fn set_webhooks(&mut self, webhooks: Arc<WebhookManager>) {
+        self.webhooks = Some(webhooks);
+    }
+
+    /// Send approved signals to the router's queue
+    pub fn attach(&self, release_tx: mpsc::Sender<NeuronSignal>) {
+        *self.release_tx.lock() = Some(release_tx);
+    }
+
+    /// Hold `output`, the response to `signal`, and the signals it forwards
+    /// until a decision. Past `max_pending` held outputs the branch fails
+    /// instead and `None` is returned.
+    pub fn hold(
+        &self,
+        signal: NeuronSignal,
+        output: &str,
+        children: Vec<NeuronSignal>,
+        reason: &str,
+    ) -> Option<PendingApproval> {
+        let now = Utc::now();
+        let chain_id = ChainTracker::chain_id_of(&signal).map(str::to_string);
+        let approval = PendingApproval {
+            id: Uuid::new_v4().to_string(),
+            org_id: chain_id
+                .as_deref()
+                .and_then(|chain_id| self.chain_tracker.get(chain_id))
+                .and_then(|record| record.org_id),
+            chain_id,
+            signal_id: signal.signal_id.to_string(),
+            neuron_id: signal.to_neuron.clone(),
+            layer: signal.layer_to.clone(),
+            reason: reason.to_string(),
+            output: output.to_string(),
+            content: children
+                .iter()
+                .find(|c| c.propagation_type == PropagationType::Forward)
+                .map(|c| c.payload.activation.content.clone()),
+            forward_to: children.iter().map(|c| c.to_neuron.clone()).collect(),
+            requested_at: now,
+            expires_at: now + chrono::Duration::seconds(self.config.timeout_secs as i64),
+            timeout_action: self.config.timeout_action,
+        };
+
+        {
+            let mut pending = self.pending.lock();
+            if pending.len() >= self.config.max_pending {
+                drop(pending);
+                warn!("Too many outputs await approval, failing the branch of neuron {}", approval.neuron_id);
+                let err = "Too many outputs are waiting for approval";
+                self.chain_tracker.record_step(&signal, Err(err), 0);
+                return None;
+            }
+            self.chain_tracker.record_gate(&signal, &approval.id, reason);
+            pending.insert(approval.id.clone(), HeldOutput {
+                approval: approval.clone(),
+                signal,
+                children,
+            });
+        }
+
+        info!(
+            target: "audit",
+            event = "approval_requested",
+            approval_id = %approval.id,
+            chain_id = approval.chain_id.as_deref().unwrap_or(""),
+            neuron_id = %approval.neuron_id,
+            reason = %approval.reason,
+            expires_at = %approval.expires_at,
+            "Output of neuron {} held for approval", approval.neuron_id
+        );
+        if let Some(webhooks) = &self.webhooks {
+            webhooks.emit_approval(&self.owner_of(&approval), &approval);
+        }
+        Some(approval)
+    }
+
+    /// Approve a held output, replacing the content its forward signals
+    /// carry with `content` when given, and send them on
+    pub async fn approve(
+        &self,
+        id: &str,
+        content: Option<String>,
+        actor: Option<&str>,
+    ) -> ServerResult<ApprovalDecision> {
+        if content.as_deref().is_some_and(|c| c.trim().is_empty()) {
+            return Err(ServerError::InvalidInput("Edited content is empty".to_string()));
+        }
+        let release_tx = self.release_tx.lock().clone();
+        let held = {
+            let mut pending = self.pending.lock();
+            if pending.get(id).is_some_and(|held| !held.children.is_empty()) && release_tx.is_none() {
+                return Err(ServerError::Internal(format!(
+                    "Approval {} holds signals but no router is attached",
+                    id
+                )));
+            }
+            pending.remove(id)
+        };
+        let Some(HeldOutput { approval, signal, mut children }) = held else {
+            return Err(ServerError::NotFound(format!("Approval {} not found", id)));
+        };
+
+        let actor = actor.unwrap_or("unknown");
+        let edited = content.is_some();
+        let output = match content {
+            Some(content) => {
+                for child in children.iter_mut().filter(|c| c.propagation_type == PropagationType::Forward) {
+                    child.payload.activation.content = content.clone();
+                }
+                content
+            }
+            None => approval.output,
+        };
+
+        self.chain_tracker.decide_gate(&signal, id, true, actor, edited);
+        self.chain_tracker.record_step(&signal, Ok(&output), children.len());
+        let released = children.len();
+        info!(
+            target: "audit",
+            event = "approval_approved",
+            approval_id = %id,
+            chain_id = approval.chain_id.as_deref().unwrap_or(""),
+            neuron_id = %approval.neuron_id,
+            actor,
+            edited,
+            released,
+            "Output of neuron {} approved", approval.neuron_id
+        );
+
+        if let Some(release_tx) = release_tx {
+            for child in children {
+                if release_tx.send(child).await.is_err() {
+                    error!("Router stopped while releasing signals of approval {}", id);
+                    break;
+                }
+            }
+        }
+        Ok(ApprovalDecision {
+            id: id.to_string(),
+            status: GateStatus::Approved,
+            decided_by: actor.to_string(),
+            edited,
+            released,
+        })
+    }
+
+    /// Reject a held output, failing its branch; the rest of the chain
+    /// carries on
+    pub fn reject(&self, id: &str, reason: Option<&str>, actor: Option<&str>) -> ServerResult<ApprovalDecision> {
+        let Some(held) = self.pending.lock().remove(id) else {
+            return Err(ServerError::NotFound(format!("Approval {} not found", id)));
+        };
+
+        let actor = actor.unwrap_or("unknown");
+        let reason = reason.map(str::trim).filter(|r| !r.is_empty()).unwrap_or("no reason given");
+        let err = format!("Rejected at approval gate by {}: {}", actor, reason);
+        self.chain_tracker.decide_gate(&held.signal, id, false, actor, false);
+        self.chain_tracker.record_step(&held.signal, Err(&err), 0);
+        info!(
+            target: "audit",
+            event = "approval_rejected",
+            approval_id = %id,
+            chain_id = held.approval.chain_id.as_deref().unwrap_or(""),
+            neuron_id = %held.approval.neuron_id,
+            actor,
+            reason,
+            "Output of neuron {} rejected", held.approval.neuron_id
+        );
+        Ok(ApprovalDecision {
+            id: id.to_string(),
+            status: GateStatus::Rejected,
+            decided_by: actor.to_string(),
+            edited: false,
+            released: 0,
+        })
+    }
+
+    /// Take the timeout action on every approval that expired by `now`
+    pub async fn resolve_expired(&self, now: DateTime<Utc>) -> Vec<ApprovalDecision> {
+        let due: Vec<String> = self
+            .pending
+            .lock()
+            .values()
+            .filter(|held| held.approval.expires_at <= now)
+            .map(|held| held.approval.id.clone())
+            .collect();
+
+        let mut decisions = Vec::new();
+        for id in due {
+            let decision = match self.config.timeout_action {
+                ApprovalTimeoutAction::Reject => self.reject(&id, Some("timed out"), Some(TIMEOUT_ACTOR)),
+                ApprovalTimeoutAction::Approve => self.approve(&id, None, Some(TIMEOUT_ACTOR)).await,
+            };
+            match decision {
+                Ok(decision) => decisions.push(decision),
+                // Decided by someone meanwhile
+                Err(ServerError::NotFound(_)) => {}
+                Err(e) => warn!("Failed to time out approval {}: {}", id, e),
+            }
+        }
+        decisions
+    }
+
+    /// Time approvals out as their deadlines pass
+    pub fn start(self: &Arc<Self>) {
+        let gates = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(TIMEOUT_INTERVAL);
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+            loop {
+                interval.tick().await;
+                gates.resolve_expired(Utc::now()).await;
+            }
+        });
+    }
+
+    /// Outputs waiting for a decision, oldest first
+    pub fn pending(&self) -> Vec<PendingApproval> {
+        let mut approvals: Vec<_> = self.pending.lock().values().map(|held| held.approval.clone()).collect();
+        approvals.sort_by(|a, b| a.requested_at.cmp(&b.requested_at).then_with(|| a.id.cmp(&b.id)));
+        approvals
+    }
+
+    pub fn get(&self, id: &str) -> Option<PendingApproval> {
+        self.pending.lock().get(id).map(|held| held.approval.clone())
+    }
+
+    /// Organization, or user without one, owning the chain of an approval
+    fn owner_of(&self, approval: &PendingApproval) -> String {
+        approval
+            .chain_id
+            .as_deref()
+            .and_then(|chain_id| self.chain_tracker.get(chain_id))
+            .and_then(|record| record.org_id.or(record.user_id))
+            .unwrap_or_else(|| "anonymous".to_string())
+    }
+}
@@ -7,13 +7,17 @@ use axum::{
     response::Response,
 };
 use std::sync::Arc;
-use hal9_core::auth::{JwtManager, ApiKeyManager, Permissions, AuthError};
+use hal9_core::auth::{JwtManager, JwtClaims, ApiKey, ApiKeyManager, UserManager, Permissions, AuthError};
+
+/// Token type set on claims built for API key requests
+pub const API_KEY_TOKEN_TYPE: &str = "api_key";
 
 /// Authentication state
 #[derive(Clone)]
 pub struct AuthState {
     pub jwt_manager: Arc<JwtManager>,
     pub api_key_manager: Arc<ApiKeyManager>,
+    pub user_manager: Arc<UserManager>,
 }
 
 /// Authenticated user info
@@ -23,6 +27,10 @@ pub struct AuthUser {
     pub username: String,
     pub role: String,
     pub permissions: Permissions,
+    /// Organization the user belongs to, if known
+    pub org_id: Option<String>,
+    /// API key the request was authenticated with, if any
+    pub api_key_id: Option<String>,
 }
 
 /// Extract bearer token from Authorization header
@@ -43,6 +51,64 @@ fn extract_api_key(req: &Request) -> Option<String> {
         .map(|s| s.to_string())
 }
 
+/// User and claims for a validated access token
+fn token_user(claims: JwtClaims) -> (AuthUser, JwtClaims) {
+    let user = AuthUser {
+        user_id: claims.sub.clone(),
+        username: claims.username.clone(),
+        role: claims.role.clone(),
+        permissions: get_role_permissions(&claims.role),
+        org_id: None,
+        api_key_id: None,
+    };
+    (user, claims)
+}
+
+/// User and claims for an API key, shaped like those of an access token for
+/// the key's owner. Permissions are the key's scopes, narrowed to what the
+/// owner's role still grants.
+pub async fn api_key_user(auth_state: &AuthState, key: &str) -> Result<(AuthUser, JwtClaims), AuthError> {
+    let (api_key, scopes) = auth_state.api_key_manager.validate_api_key(key).await?;
+    let owner = auth_state.user_manager.get_user(&api_key.user_id).await?;
+    if !owner.is_active {
+        return Err(AuthError::InvalidCredentials);
+    }
+    
+    let claims = api_key_claims(&api_key, &owner.username, &owner.role);
+    let user = AuthUser {
+        user_id: owner.id,
+        username: owner.username,
+        permissions: scopes.intersect(&get_role_permissions(&owner.role)),
+        role: owner.role,
+        org_id: api_key.org_id,
+        api_key_id: Some(api_key.id),
+    };
+    Ok((user, claims))
+}
+
+/// Claims for a request made with `api_key` on behalf of its owner
+fn api_key_claims(api_key: &ApiKey, username: &str, role: &str) -> JwtClaims {
+    JwtClaims {
+        sub: api_key.user_id.clone(),
+        username: username.to_string(),
+        role: role.to_string(),
+        iat: api_key.created_at,
+        exp: api_key.expires_at.unwrap_or(i64::MAX),
+        nbf: api_key.created_at,
+        jti: api_key.id.clone(),
+        token_type: API_KEY_TOKEN_TYPE.to_string(),
+    }
+}
+
+/// Status for a rejected API key
+fn api_key_status(err: &AuthError) -> StatusCode {
+    match err {
+        AuthError::RateLimitExceeded | AuthError::BudgetExhausted => StatusCode::TOO_MANY_REQUESTS,
+        AuthError::DatabaseError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        _ => StatusCode::UNAUTHORIZED,
+    }
+}
+
 /// Authentication middleware
 pub async fn auth_middleware(
     State(auth_state): State<AuthState>,
@@ -53,12 +119,7 @@ pub async fn auth_middleware(
     if let Some(token) = extract_bearer_token(&req) {
         match auth_state.jwt_manager.validate_access_token(&token) {
             Ok(claims) => {
-                let user = AuthUser {
-                    user_id: claims.sub.clone(),
-                    username: claims.username.clone(),
-                    role: claims.role.clone(),
-                    permissions: get_role_permissions(&claims.role),
-                };
+                let (user, claims) = token_user(claims);
                 req.extensions_mut().insert(user);
                 req.extensions_mut().insert(claims);
                 return Ok(next.run(req).await);
@@ -70,18 +131,13 @@ pub async fn auth_middleware(
     
     // Try API key
     if let Some(api_key) = extract_api_key(&req) {
-        match auth_state.api_key_manager.validate_api_key(&api_key).await {
-            Ok((key_info, permissions)) => {
-                let user = AuthUser {
-                    user_id: key_info.user_id.clone(),
-                    username: format!("api_key_{}", key_info.name),
-                    role: "api_key".to_string(),
-                    permissions,
-                };
+        match api_key_user(&auth_state, &api_key).await {
+            Ok((user, claims)) => {
                 req.extensions_mut().insert(user);
+                req.extensions_mut().insert(claims);
                 return Ok(next.run(req).await);
             }
-            Err(_) => return Err(StatusCode::UNAUTHORIZED),
+            Err(e) => return Err(api_key_status(&e)),
         }
     }
     
@@ -97,25 +153,22 @@ pub async fn optional_auth_middleware(
     // Try JWT token
     if let Some(token) = extract_bearer_token(&req) {
         if let Ok(claims) = auth_state.jwt_manager.validate_access_token(&token) {
-            let user = AuthUser {
-                user_id: claims.sub.clone(),
-                username: claims.username.clone(),
-                role: claims.role.clone(),
-                permissions: get_role_permissions(&claims.role),
-            };
+            let (user, claims) = token_user(claims);
             req.extensions_mut().insert(user);
             req.extensions_mut().insert(claims);
         }
     } else if let Some(api_key) = extract_api_key(&req) {
-        // Try API key
-        if let Ok((key_info, permissions)) = auth_state.api_key_manager.validate_api_key(&api_key).await {
-            let user = AuthUser {
-                user_id: key_info.user_id.clone(),
-                username: format!("api_key_{}", key_info.name),
-                role: "api_key".to_string(),
-                permissions,
-            };
-            req.extensions_mut().insert(user);
+        // Try API key; a key over its limits is refused rather than
+        // let through anonymously
+        match api_key_user(&auth_state, &api_key).await {
+            Ok((user, claims)) => {
+                req.extensions_mut().insert(user);
+                req.extensions_mut().insert(claims);
+            }
+            Err(e @ (AuthError::RateLimitExceeded | AuthError::BudgetExhausted)) => {
+                return Err(api_key_status(&e));
+            }
+            Err(_) => {}
         }
     }
     
@@ -137,7 +190,7 @@ pub fn require_permission(permission: hal9_core::auth::Permission) -> impl Fn(Re
 }
 
 /// Get permissions for a role
-fn get_role_permissions(role: &str) -> Permissions {
+pub fn get_role_permissions(role: &str) -> Permissions {
     use hal9_core::auth::UserRole;
     
     match role {
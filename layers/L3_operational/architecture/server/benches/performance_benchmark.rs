@@ -78,11 +78,15 @@ fn create_performance_config() -> ServerConfig {
             mock_responses,
             fallback_to_mock: false,
             cost_controls: CostControls::default(),
+            scenarios: Default::default(),
         },
         monitoring: MonitoringConfig {
             enabled: false, // Disable monitoring for pure performance
             metrics_interval: 60,
             log_level: "error".to_string(),
+            log_index: Default::default(),
+            slos: Vec::new(),
+            slo_alerts: Default::default(),
         },
     }
 }
@@ -298,13 +302,43 @@ fn benchmark_layer_latency(c: &mut Criterion) {
     group.finish();
 }
 
+/// Cost of indexing log events, compared with the same events filtered out
+fn benchmark_log_indexing(c: &mut Criterion) {
+    use hal9_core::config::LogIndexConfig;
+    use hal9_server::log_index::LogIndex;
+    use hal9_server::logging::LogLevelControl;
+    use tracing_subscriber::layer::SubscriberExt;
+    
+    let mut group = c.benchmark_group("log_indexing");
+    
+    for (name, base) in [("indexed", "info"), ("filtered", "error")] {
+        let index = Arc::new(LogIndex::new(&LogIndexConfig::default()));
+        let (filter, _levels) = LogLevelControl::new(base);
+        let subscriber = tracing_subscriber::registry()
+            .with(filter)
+            .with(index.layer());
+        
+        tracing::subscriber::with_default(subscriber, || {
+            let span = tracing::info_span!("signal", chain_id = "bench-chain", neuron_id = "bench-l4-1", layer = "L4");
+            let _guard = span.enter();
+            
+            group.bench_function(name, |b| {
+                b.iter(|| tracing::info!(event = "signal_processed", latency_ms = black_box(12u64), "Neuron processed signal"));
+            });
+        });
+    }
+    
+    group.finish();
+}
+
 criterion_group!(
     benches,
     benchmark_single_signal,
     benchmark_parallel_signals,
     benchmark_cached_responses,
     benchmark_batch_processing,
-    benchmark_layer_latency
+    benchmark_layer_latency,
+    benchmark_log_indexing
 );
 
 criterion_main!(benches);
\ No newline at end of file
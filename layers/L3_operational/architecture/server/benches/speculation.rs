@@ -0,0 +1,119 @@
+//! Chain latency of a standard L4 -> L3 -> L2 chain with and without
+//! speculative execution
+//!
+//! Each neuron takes 200ms to answer, streamed a line at a time. The L4 and
+//! L3 responses close their forward instruction three lines into eleven, so
+//! with speculation on those layers each child starts about 55ms into its
+//! parent's call instead of after it: the chain takes roughly 200 + 55 + 55
+//! rather than 600ms, plus the router's batching delay on every hop.
+
+use async_trait::async_trait;
+use criterion::{criterion_group, criterion_main, Criterion};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+use hal9_core::config::SpeculationConfig;
+use hal9_core::{NeuronConfig, NeuronSignal, Result};
+use hal9_server::chain_tracker::ChainTracker;
+use hal9_server::claude::{ClaudeInterface, TokenUsage};
+use hal9_server::neuron::{ManagedNeuron, NeuronRegistry};
+use hal9_server::router::{RoutingTable, SignalRouter};
+use hal9_server::speculation::Speculator;
+
+const CALL: Duration = Duration::from_millis(200);
+
+/// Answers `response` after `CALL`, streaming it evenly when asked to
+struct Timed {
+    response: String,
+}
+
+#[async_trait]
+impl ClaudeInterface for Timed {
+    async fn send_message(&self, _message: &str) -> Result<String> {
+        tokio::time::sleep(CALL).await;
+        Ok(self.response.clone())
+    }
+
+    async fn stream_message(&self, _message: &str, partial: &mpsc::UnboundedSender<String>) -> Result<String> {
+        let lines: Vec<&str> = self.response.split_inclusive('\n').collect();
+        for line in &lines {
+            tokio::time::sleep(CALL / lines.len() as u32).await;
+            let _ = partial.send(line.to_string());
+        }
+        Ok(self.response.clone())
+    }
+
+    fn system_prompt(&self) -> &str {
+        ""
+    }
+
+    fn last_token_usage(&self) -> Option<TokenUsage> {
+        None
+    }
+}
+
+/// A response forwarding to `target` early, then explaining itself
+fn forwarding(target: &str) -> String {
+    let rationale: String = (1..=8).map(|line| format!("Rationale line {}\n", line)).collect();
+    format!("CONTENT:\nwork on the parser\nFORWARD_TO: {}\n{}", target, rationale)
+}
+
+fn config(id: &str, layer: &str, forward: &[&str]) -> NeuronConfig {
+    NeuronConfig {
+        id: id.to_string(),
+        layer: layer.to_string(),
+        claude_command: "claude".to_string(),
+        system_prompt: None,
+        forward_connections: forward.iter().map(|s| s.to_string()).collect(),
+        backward_connections: vec![],
+        settings: HashMap::new(),
+    }
+}
+
+/// Run one chain through a fresh router
+async fn run_chain(speculate: bool) {
+    let configs = [
+        (config("planner", "L4", &["designer"]), forwarding("designer")),
+        (config("designer", "L3", &["coder"]), forwarding("coder")),
+        (config("coder", "L2", &[]), "RESULT: done".to_string()),
+    ];
+    let registry = Arc::new(NeuronRegistry::new());
+    for (config, response) in &configs {
+        let neuron = ManagedNeuron::new(config.clone(), Box::new(Timed { response: response.clone() })).unwrap();
+        registry.register(neuron).await.unwrap();
+    }
+    let routing_table = Arc::new(RoutingTable::new());
+    routing_table.build_from_configs(&configs.iter().map(|(config, _)| config.clone()).collect::<Vec<_>>());
+
+    let chain_tracker = Arc::new(ChainTracker::new());
+    let mut router = SignalRouter::new(registry.clone(), routing_table);
+    router.set_chain_tracker(chain_tracker.clone());
+    if speculate {
+        let config = SpeculationConfig { layers: vec!["L4".to_string(), "L3".to_string()], ..Default::default() };
+        router.set_speculation(Arc::new(Speculator::new(&config).unwrap()));
+    }
+    router.start().await.unwrap();
+
+    let mut finished = chain_tracker.subscribe();
+    let mut root = NeuronSignal::forward("client", "planner", "L5", "L4", "build a parser".to_string());
+    chain_tracker.start(&mut root);
+    router.send_signal(root).await.unwrap();
+    finished.recv().await.unwrap();
+    router.shutdown().await.unwrap();
+}
+
+fn bench_chain_latency(c: &mut Criterion) {
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    let mut group = c.benchmark_group("three_layer_chain");
+    group.sample_size(10);
+
+    group.bench_function("sequential", |b| b.iter(|| rt.block_on(run_chain(false))));
+    group.bench_function("speculative", |b| b.iter(|| rt.block_on(run_chain(true))));
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_chain_latency);
+criterion_main!(benches);
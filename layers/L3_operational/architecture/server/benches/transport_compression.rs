@@ -0,0 +1,84 @@
+//! Inter-server transport benchmarks: frame encode + decode with and without
+//! zstd, and batched against one-by-one signal frames
+//!
+//! Payloads are cut from this crate's own source, which compresses about as
+//! well as the code and prose L2 neurons exchange. No two payloads overlap,
+//! so zstd cannot profit from repeats that real traffic would not have.
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+use hal9_core::NeuronSignal;
+use hal9_server::network::protocol::{Compression, MessageCodec, NetworkMessage};
+
+const PAYLOAD_SIZES: [usize; 4] = [256, 4 * 1024, 64 * 1024, 512 * 1024];
+
+const ZSTD: Compression = Compression { level: 3, threshold: 1024 };
+
+/// The crate's top-level source files, concatenated
+fn corpus() -> String {
+    let mut files: Vec<_> = std::fs::read_dir(env!("CARGO_MANIFEST_DIR"))
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "rs"))
+        .collect();
+    files.sort();
+    files.iter().map(|path| std::fs::read_to_string(path).unwrap()).collect()
+}
+
+/// `size` bytes of the corpus starting at `offset`
+fn payload(corpus: &str, offset: usize, size: usize) -> String {
+    String::from_utf8_lossy(&corpus.as_bytes()[offset..offset + size]).into_owned()
+}
+
+fn signal(content: String) -> NeuronSignal {
+    NeuronSignal::forward("neuron-l3", "neuron-l2", "L3", "L2", content)
+}
+
+fn round_trip(msg: &NetworkMessage, compression: Option<&Compression>) -> NetworkMessage {
+    let frame = MessageCodec::encode_with(msg, compression).unwrap();
+    MessageCodec::decode_frame(&frame.bytes).unwrap().message
+}
+
+fn bench_compression(c: &mut Criterion) {
+    let corpus = corpus();
+    let mut group = c.benchmark_group("transport_frame");
+
+    for size in PAYLOAD_SIZES {
+        let msg = NetworkMessage::Signal(Box::new(signal(payload(&corpus, 0, size))));
+        group.throughput(Throughput::Bytes(size as u64));
+
+        group.bench_with_input(BenchmarkId::new("raw", size), &msg, |b, msg| {
+            b.iter(|| round_trip(black_box(msg), None))
+        });
+        group.bench_with_input(BenchmarkId::new("zstd", size), &msg, |b, msg| {
+            b.iter(|| round_trip(black_box(msg), Some(&ZSTD)))
+        });
+    }
+
+    group.finish();
+}
+
+fn bench_batching(c: &mut Criterion) {
+    let corpus = corpus();
+    let mut group = c.benchmark_group("transport_batch");
+    let signals: Vec<NeuronSignal> = (0..32)
+        .map(|i| signal(payload(&corpus, i * 512, 512)))
+        .collect();
+    group.throughput(Throughput::Elements(signals.len() as u64));
+
+    group.bench_function("one_frame_per_signal", |b| {
+        b.iter(|| {
+            for signal in &signals {
+                round_trip(&NetworkMessage::Signal(Box::new(signal.clone())), Some(&ZSTD));
+            }
+        })
+    });
+    group.bench_function("one_batch", |b| {
+        b.iter(|| round_trip(&NetworkMessage::Batch { signals: signals.clone() }, Some(&ZSTD)))
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_compression, bench_batching);
+criterion_main!(benches);
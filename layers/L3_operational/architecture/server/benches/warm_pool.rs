@@ -0,0 +1,101 @@
+//! Cold-start benchmarks: the first signal to a process-backed neuron,
+//! instantiated on demand against checked out of a warm pool
+//!
+//! The neuron is a `cat` process answering each signal with its content, so
+//! the measured start-up cost is a real process spawn rather than a sleep.
+
+use async_trait::async_trait;
+use criterion::{criterion_group, criterion_main, Criterion};
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStdout, Command, Stdio};
+use std::sync::Arc;
+
+use hal9_core::config::WarmPoolConfig;
+use hal9_core::neuron::{NeuronHealth, NeuronState};
+use hal9_core::{Error, Layer, NeuronInterface, NeuronSignal, Result};
+use hal9_server::warm_pool::{InstanceFactory, WarmPool};
+
+struct ProcessNeuron {
+    child: parking_lot::Mutex<Child>,
+    stdout: parking_lot::Mutex<BufReader<ChildStdout>>,
+}
+
+#[async_trait]
+impl NeuronInterface for ProcessNeuron {
+    fn id(&self) -> &str {
+        "process-neuron"
+    }
+
+    fn layer(&self) -> Layer {
+        Layer::L2
+    }
+
+    async fn process_signal(&self, signal: &NeuronSignal) -> Result<String> {
+        let mut child = self.child.lock();
+        let stdin = child.stdin.as_mut().ok_or_else(|| Error::Process("stdin closed".to_string()))?;
+        writeln!(stdin, "{}", signal.payload.activation.content).map_err(|e| Error::Process(e.to_string()))?;
+        let mut line = String::new();
+        self.stdout.lock().read_line(&mut line).map_err(|e| Error::Process(e.to_string()))?;
+        Ok(line)
+    }
+
+    async fn health(&self) -> Result<NeuronHealth> {
+        Ok(NeuronHealth { state: NeuronState::Running, last_signal: None, signals_processed: 0, errors_count: 0, uptime_seconds: 0 })
+    }
+
+    async fn shutdown(&self) -> Result<()> {
+        let mut child = self.child.lock();
+        let _ = child.kill();
+        let _ = child.wait();
+        Ok(())
+    }
+}
+
+struct ProcessFactory;
+
+#[async_trait]
+impl InstanceFactory for ProcessFactory {
+    async fn instantiate(&self) -> Result<Arc<dyn NeuronInterface>> {
+        let mut child = Command::new("cat")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .map_err(|e| Error::Process(e.to_string()))?;
+        let stdout = BufReader::new(child.stdout.take().expect("stdout is piped"));
+        Ok(Arc::new(ProcessNeuron { child: parking_lot::Mutex::new(child), stdout: parking_lot::Mutex::new(stdout) }))
+    }
+}
+
+fn bench_cold_start(c: &mut Criterion) {
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    let signal = NeuronSignal::forward("neuron-l3", "process-neuron", "L3", "L2", "ping".to_string());
+    let mut group = c.benchmark_group("first_signal");
+
+    group.bench_function("on_demand", |b| {
+        b.iter(|| rt.block_on(async {
+            let neuron = ProcessFactory.instantiate().await.unwrap();
+            let output = neuron.process_signal(&signal).await.unwrap();
+            neuron.shutdown().await.unwrap();
+            output
+        }))
+    });
+
+    let pool = WarmPool::new(WarmPoolConfig {
+        sizes: HashMap::from([("process".to_string(), 4)]),
+        ..WarmPoolConfig::default()
+    });
+    pool.register("process", Arc::new(ProcessFactory));
+    rt.block_on(pool.refresh());
+    group.bench_function("warm_pool", |b| {
+        b.iter(|| rt.block_on(async {
+            let neuron = pool.checkout("process").await.unwrap();
+            neuron.process_signal(&signal).await.unwrap()
+        }))
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_cold_start);
+criterion_main!(benches);
@@ -0,0 +1,778 @@
+//! Billing export: nightly usage files for the billing pipeline
+//!
+//! The export job writes one file per organization and finished UTC day,
+//! as CSV and as JSON, from the usage rows [`crate::org_usage`] records in
+//! the auth database, and delivers them to the destination configured under
+//! `billing_export`: a local directory, an S3-compatible bucket, or a URL
+//! the JSON file is POSTed to, signed like outbound webhooks. Each run
+//! covers the last `lookback_days` finished days.
+//!
+//! Files are deterministic: exporting a day again yields the same bytes and
+//! the same `content_hash`, a SHA-256 of the `usage` object, so a run that
+//! finds nothing changed delivers nothing. A day whose usage changed after
+//! it was delivered, typically because a chain's cost was written late by a
+//! retry, gets an amended file: the next `revision`, flagged `correction`,
+//! naming the hash it `supersedes`. Earlier revisions are left in place.
+//! Every delivered file is recorded in the `billing_exports` manifest.
+//!
+//! Storage is measured once per day, when the day is first exported, and
+//! kept with the manifest, so later revisions of a day report the same
+//! bytes.
+//!
+//! # Schema (version 1)
+//!
+//! Objects are keyed `{org_id}/{date}/usage-r{revision}.json` and `.csv`;
+//! webhook deliveries carry the JSON key as their delivery ID. The JSON
+//! file is a [`BillingFile`]:
+//!
+//! ```json
+//! {
+//!   "schema_version": 1,
+//!   "revision": 2,
+//!   "correction": true,
+//!   "supersedes": "<hex sha256 of revision 1>",
+//!   "content_hash": "<hex sha256 of usage>",
+//!   "usage": {
+//!     "org_id": "acme",
+//!     "date": "2026-10-14",
+//!     "chains": 12,
+//!     "prompt_tokens": 48000,
+//!     "completion_tokens": 9100,
+//!     "cost_usd": 0.2715,
+//!     "by_model": [{"model": "claude-3-haiku-20240307", "prompt_tokens": 48000,
+//!                   "completion_tokens": 9100, "cost_usd": 0.2715}],
+//!     "artifact_bytes": 20480,
+//!     "memory_bytes": 1048576
+//!   }
+//! }
+//! ```
+//!
+//! The CSV file has the columns of [`CSV_HEADER`]: a `*` row for the day's
+//! totals, the only row with `chains` and storage bytes, then one row per
+//! model. Costs are rounded to the micro-dollar.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Days, NaiveDate, NaiveTime, TimeZone, Utc};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::Row;
+use tracing::{debug, error, info, warn};
+
+use hal9_core::{
+    config::{AuthConfig, BillingDestination, BillingExportConfig},
+    memory::{FsObjectStore, ObjectStore, SqliteMemoryStore},
+    secrets::SecretString,
+    sqlite::SqlitePools,
+    Error, Result,
+};
+
+use crate::{
+    error::{ServerError, ServerResult},
+    memory_tiering::S3ObjectStore,
+    templates::TemplateLibrary,
+    webhooks::{sign, to_hex, DELIVERY_HEADER, EVENT_HEADER, SIGNATURE_HEADER, TIMESTAMP_HEADER},
+};
+
+/// Version of the file schema, written into every file
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Event name of webhook deliveries
+pub const EXPORT_EVENT: &str = "billing.export";
+
+/// Columns of the CSV files
+pub const CSV_HEADER: [&str; 13] = [
+    "schema_version",
+    "org_id",
+    "date",
+    "revision",
+    "correction",
+    "content_hash",
+    "model",
+    "prompt_tokens",
+    "completion_tokens",
+    "cost_usd",
+    "chains",
+    "artifact_bytes",
+    "memory_bytes",
+];
+
+/// Model name of the CSV row holding a day's totals
+pub const TOTAL_ROW: &str = "*";
+
+/// Check the billing export settings
+pub fn validate(config: &BillingExportConfig, auth: &AuthConfig) -> Result<()> {
+    let invalid = |message: &str| Err(Error::Config(format!("Invalid billing export config: {}", message)));
+    if !config.enabled {
+        return Ok(());
+    }
+    if !auth.enabled {
+        return invalid("usage is only recorded per organization with auth enabled");
+    }
+    if config.interval_secs == 0 {
+        return invalid("interval_secs must be positive");
+    }
+    if config.lookback_days == 0 {
+        return invalid("lookback_days must be positive");
+    }
+    Destination::from_config(&config.destination).map(|_| ())
+}
+
+/// Round a cost to the micro-dollar, so sums over the same rows compare
+/// equal whatever order they were added in
+fn usd(cost: f64) -> f64 {
+    (cost * 1e6).round() / 1e6
+}
+
+fn day_start(date: NaiveDate) -> DateTime<Utc> {
+    Utc.from_utc_datetime(&date.and_time(NaiveTime::MIN))
+}
+
+fn storage_error(e: sqlx::Error) -> Error {
+    Error::Storage(format!("Billing export: {}", e))
+}
+
+/// Bytes each organization stores in one place
+#[async_trait]
+pub trait StorageMeter: Send + Sync {
+    async fn bytes_by_org(&self) -> Result<HashMap<String, u64>>;
+}
+
+#[async_trait]
+impl StorageMeter for SqliteMemoryStore {
+    async fn bytes_by_org(&self) -> Result<HashMap<String, u64>> {
+        self.org_bytes().await
+    }
+}
+
+#[async_trait]
+impl StorageMeter for TemplateLibrary {
+    async fn bytes_by_org(&self) -> Result<HashMap<String, u64>> {
+        self.artifact_bytes().await
+    }
+}
+
+/// Tokens and cost of one model over a day
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModelUsage {
+    pub model: String,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub cost_usd: f64,
+}
+
+/// An organization's usage over one UTC day
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DailyUsage {
+    pub org_id: String,
+    pub date: NaiveDate,
+    /// Chains started
+    pub chains: u64,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub cost_usd: f64,
+    /// By model name
+    pub by_model: Vec<ModelUsage>,
+    /// Template artifacts stored, as measured when the day was first
+    /// exported
+    pub artifact_bytes: u64,
+    /// Memory content stored in both tiers, measured likewise
+    pub memory_bytes: u64,
+}
+
+impl DailyUsage {
+    fn new(org_id: &str, date: NaiveDate) -> Self {
+        Self {
+            org_id: org_id.to_string(),
+            date,
+            chains: 0,
+            prompt_tokens: 0,
+            completion_tokens: 0,
+            cost_usd: 0.0,
+            by_model: Vec::new(),
+            artifact_bytes: 0,
+            memory_bytes: 0,
+        }
+    }
+
+    /// SHA-256 of the compact JSON of this usage, fields in schema order
+    pub fn content_hash(&self) -> String {
+        to_hex(&Sha256::digest(serde_json::to_vec(self).unwrap_or_default()))
+    }
+}
+
+/// One usage file, as delivered in JSON
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BillingFile {
+    pub schema_version: u32,
+    /// 1 for a day's first file, one more for each amendment
+    pub revision: u32,
+    /// Whether this file amends an earlier one
+    pub correction: bool,
+    /// Content hash of the file this one amends
+    pub supersedes: Option<String>,
+    /// [`DailyUsage::content_hash`] of `usage`
+    pub content_hash: String,
+    pub usage: DailyUsage,
+}
+
+impl BillingFile {
+    /// Object key of the file with `extension`
+    pub fn key(&self, extension: &str) -> String {
+        format!("{}/{}/usage-r{}.{}", self.usage.org_id, self.usage.date, self.revision, extension)
+    }
+
+    pub fn to_json(&self) -> Vec<u8> {
+        serde_json::to_vec_pretty(self).unwrap_or_default()
+    }
+
+    pub fn to_csv(&self) -> String {
+        let usage = &self.usage;
+        let mut writer = csv::Writer::from_writer(Vec::new());
+        let _ = writer.write_record(CSV_HEADER);
+
+        let common = [
+            SCHEMA_VERSION.to_string(),
+            usage.org_id.clone(),
+            usage.date.to_string(),
+            self.revision.to_string(),
+            self.correction.to_string(),
+            self.content_hash.clone(),
+        ];
+        let mut row = |model: &str, prompt: u64, completion: u64, cost: f64, totals: Option<[u64; 3]>| {
+            let mut record = common.to_vec();
+            record.extend([model.to_string(), prompt.to_string(), completion.to_string(), cost.to_string()]);
+            match totals {
+                Some(totals) => record.extend(totals.map(|total| total.to_string())),
+                None => record.extend([String::new(), String::new(), String::new()]),
+            }
+            let _ = writer.write_record(&record);
+        };
+        row(
+            TOTAL_ROW,
+            usage.prompt_tokens,
+            usage.completion_tokens,
+            usage.cost_usd,
+            Some([usage.chains, usage.artifact_bytes, usage.memory_bytes]),
+        );
+        for model in &usage.by_model {
+            row(&model.model, model.prompt_tokens, model.completion_tokens, model.cost_usd, None);
+        }
+
+        String::from_utf8(writer.into_inner().unwrap_or_default()).unwrap_or_default()
+    }
+}
+
+/// A delivered file, as recorded in the manifest
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ExportedFile {
+    pub org_id: String,
+    pub date: NaiveDate,
+    pub revision: u32,
+    pub correction: bool,
+    pub supersedes: Option<String>,
+    pub content_hash: String,
+    pub cost_usd: f64,
+    /// `filesystem`, `s3` or `webhook`
+    pub destination: String,
+    /// Object keys written, or the delivery ID of a webhook
+    pub keys: Vec<String>,
+    pub delivered_at: DateTime<Utc>,
+}
+
+/// Outcome of an export run
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ExportReport {
+    pub delivered: Vec<ExportedFile>,
+    /// Files already delivered with the same content
+    pub unchanged: usize,
+    /// Files that could not be delivered, retried on the next run
+    pub failed: Vec<String>,
+}
+
+/// Where files are delivered
+pub enum Destination {
+    /// CSV and JSON objects
+    Store(Arc<dyn ObjectStore>),
+    /// The JSON file POSTed to a URL
+    Webhook {
+        client: reqwest::Client,
+        url: reqwest::Url,
+        secret: SecretString,
+    },
+}
+
+impl Destination {
+    pub fn from_config(config: &BillingDestination) -> Result<Self> {
+        Ok(match config {
+            BillingDestination::Filesystem { dir } => Destination::Store(Arc::new(FsObjectStore::new(dir))),
+            BillingDestination::S3 { endpoint, bucket, region, prefix, access_key_id, secret_access_key } => {
+                Destination::Store(Arc::new(S3ObjectStore::new(
+                    endpoint,
+                    bucket,
+                    region,
+                    prefix,
+                    access_key_id,
+                    secret_access_key.clone(),
+                )?))
+            }
+            BillingDestination::Webhook { url, secret } => {
+                if secret.expose().is_empty() {
+                    return Err(Error::Config("Billing webhook needs a signing secret".to_string()));
+                }
+                Destination::Webhook {
+                    client: reqwest::Client::new(),
+                    url: reqwest::Url::parse(url)
+                        .map_err(|e| Error::Config(format!("Invalid billing webhook URL '{}': {}", url, e)))?,
+                    secret: secret.clone(),
+                }
+            }
+        })
+    }
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Destination::Store(store) => store.kind(),
+            Destination::Webhook { .. } => "webhook",
+        }
+    }
+
+    /// Deliver `file`, returning the keys it was delivered under
+    async fn deliver(&self, file: &BillingFile) -> Result<Vec<String>> {
+        let (csv_key, json_key) = (file.key("csv"), file.key("json"));
+        match self {
+            // The JSON object last, so its presence means both are complete
+            Destination::Store(store) => {
+                store.put(&csv_key, file.to_csv().into_bytes()).await?;
+                store.put(&json_key, file.to_json()).await?;
+                Ok(vec![csv_key, json_key])
+            }
+            Destination::Webhook { client, url, secret } => {
+                let body = file.to_json();
+                let timestamp = Utc::now().timestamp();
+                let response = client
+                    .post(url.clone())
+                    .header(reqwest::header::CONTENT_TYPE, "application/json")
+                    .header(EVENT_HEADER, EXPORT_EVENT)
+                    .header(DELIVERY_HEADER, &json_key)
+                    .header(TIMESTAMP_HEADER, timestamp.to_string())
+                    .header(SIGNATURE_HEADER, sign(secret.expose(), timestamp, &body))
+                    .body(body)
+                    .send()
+                    .await
+                    .map_err(|e| Error::Network(format!("Billing webhook failed: {}", e)))?;
+                if !response.status().is_success() {
+                    return Err(Error::Network(format!("Billing webhook answered {}", response.status())));
+                }
+                Ok(vec![json_key])
+            }
+        }
+    }
+}
+
+/// Builds, delivers and records the usage files
+pub struct BillingExporter {
+    config: BillingExportConfig,
+    /// The auth database, with the usage rows
+    pools: SqlitePools,
+    destination: Destination,
+    artifacts: Option<Arc<dyn StorageMeter>>,
+    memory: Option<Arc<dyn StorageMeter>>,
+    last_run: Mutex<Option<ExportReport>>,
+    /// One run at a time
+    running: tokio::sync::Mutex<()>,
+}
+
+impl BillingExporter {
+    pub fn new(config: BillingExportConfig, pools: impl Into<SqlitePools>, destination: Destination) -> Self {
+        Self {
+            config,
+            pools: pools.into(),
+            destination,
+            artifacts: None,
+            memory: None,
+            last_run: Mutex::new(None),
+            running: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    /// Measure template artifacts with `meter`
+    pub fn with_artifacts(mut self, meter: Arc<dyn StorageMeter>) -> Self {
+        self.artifacts = Some(meter);
+        self
+    }
+
+    /// Measure memory with `meter`
+    pub fn with_memory(mut self, meter: Arc<dyn StorageMeter>) -> Self {
+        self.memory = Some(meter);
+        self
+    }
+
+    pub fn config(&self) -> &BillingExportConfig {
+        &self.config
+    }
+
+    pub fn last_run(&self) -> Option<ExportReport> {
+        self.last_run.lock().clone()
+    }
+
+    /// Create the storage and manifest tables
+    pub async fn initialize(&self) -> Result<()> {
+        let statements = [
+            r#"
+            CREATE TABLE IF NOT EXISTS billing_storage_days (
+                date TEXT PRIMARY KEY,
+                measured_at INTEGER NOT NULL
+            )
+            "#,
+            r#"
+            CREATE TABLE IF NOT EXISTS billing_storage (
+                org_id TEXT NOT NULL,
+                date TEXT NOT NULL,
+                artifact_bytes INTEGER NOT NULL,
+                memory_bytes INTEGER NOT NULL,
+                PRIMARY KEY (org_id, date)
+            )
+            "#,
+            r#"
+            CREATE TABLE IF NOT EXISTS billing_exports (
+                org_id TEXT NOT NULL,
+                date TEXT NOT NULL,
+                revision INTEGER NOT NULL,
+                correction BOOLEAN NOT NULL,
+                supersedes TEXT,
+                content_hash TEXT NOT NULL,
+                cost_usd REAL NOT NULL,
+                destination TEXT NOT NULL,
+                keys TEXT NOT NULL,
+                delivered_at INTEGER NOT NULL,
+                PRIMARY KEY (org_id, date, revision)
+            )
+            "#,
+            "CREATE INDEX IF NOT EXISTS idx_billing_exports_date ON billing_exports(date, org_id)",
+        ];
+        for statement in statements {
+            sqlx::query(statement).execute(self.pools.writer()).await.map_err(storage_error)?;
+        }
+        Ok(())
+    }
+
+    /// Record what each organization stores against `date`, unless it was
+    /// measured before
+    async fn measure_storage(&self, date: NaiveDate, now: DateTime<Utc>) -> Result<()> {
+        let measured: Option<i64> = sqlx::query_scalar("SELECT measured_at FROM billing_storage_days WHERE date = $1")
+            .bind(date.to_string())
+            .fetch_optional(self.pools.reader())
+            .await
+            .map_err(storage_error)?;
+        if measured.is_some() {
+            return Ok(());
+        }
+
+        let mut bytes: BTreeMap<String, (u64, u64)> = BTreeMap::new();
+        if let Some(meter) = &self.artifacts {
+            for (org_id, size) in meter.bytes_by_org().await? {
+                bytes.entry(org_id).or_default().0 = size;
+            }
+        }
+        if let Some(meter) = &self.memory {
+            for (org_id, size) in meter.bytes_by_org().await? {
+                bytes.entry(org_id).or_default().1 = size;
+            }
+        }
+
+        let bytes = &bytes;
+        self.pools
+            .write(|pool| async move {
+                let mut tx = pool.begin().await?;
+                let inserted = sqlx::query(
+                    "INSERT INTO billing_storage_days (date, measured_at) VALUES ($1, $2) ON CONFLICT(date) DO NOTHING",
+                )
+                .bind(date.to_string())
+                .bind(now.timestamp_millis())
+                .execute(&mut *tx)
+                .await?;
+                if inserted.rows_affected() == 0 {
+                    return tx.rollback().await;
+                }
+                for (org_id, (artifact_bytes, memory_bytes)) in bytes {
+                    sqlx::query(
+                        "INSERT INTO billing_storage (org_id, date, artifact_bytes, memory_bytes) VALUES ($1, $2, $3, $4)",
+                    )
+                    .bind(org_id)
+                    .bind(date.to_string())
+                    .bind(*artifact_bytes as i64)
+                    .bind(*memory_bytes as i64)
+                    .execute(&mut *tx)
+                    .await?;
+                }
+                tx.commit().await
+            })
+            .await
+            .map_err(storage_error)
+    }
+
+    /// Usage of every organization with chains, model steps or stored
+    /// bytes on `date`, by organization ID. Storage is measured now if the
+    /// day has not been.
+    pub async fn usage(&self, date: NaiveDate, now: DateTime<Utc>) -> Result<Vec<DailyUsage>> {
+        self.measure_storage(date, now).await?;
+        let pool = self.pools.reader();
+        let (from, to) = (day_start(date).timestamp_millis(), day_start(date + Days::new(1)).timestamp_millis());
+        let mut usage: BTreeMap<String, DailyUsage> = BTreeMap::new();
+
+        let chains = sqlx::query(
+            "SELECT org_id, COUNT(*) AS chains FROM usage_chains WHERE created_at >= $1 AND created_at < $2 GROUP BY org_id",
+        )
+        .bind(from)
+        .bind(to)
+        .fetch_all(pool)
+        .await
+        .map_err(storage_error)?;
+        for row in chains {
+            let org_id: String = row.get("org_id");
+            usage.entry(org_id.clone()).or_insert_with(|| DailyUsage::new(&org_id, date)).chains =
+                row.get::<i64, _>("chains") as u64;
+        }
+
+        let models = sqlx::query(
+            r#"
+            SELECT org_id, model, SUM(prompt_tokens) AS prompt_tokens,
+                   SUM(completion_tokens) AS completion_tokens, SUM(cost_usd) AS cost_usd
+            FROM usage_steps
+            WHERE created_at >= $1 AND created_at < $2
+            GROUP BY org_id, model
+            ORDER BY org_id, model
+            "#,
+        )
+        .bind(from)
+        .bind(to)
+        .fetch_all(pool)
+        .await
+        .map_err(storage_error)?;
+        for row in models {
+            let org_id: String = row.get("org_id");
+            let day = usage.entry(org_id.clone()).or_insert_with(|| DailyUsage::new(&org_id, date));
+            day.by_model.push(ModelUsage {
+                model: row.get("model"),
+                prompt_tokens: row.get::<i64, _>("prompt_tokens") as u64,
+                completion_tokens: row.get::<i64, _>("completion_tokens") as u64,
+                cost_usd: usd(row.get("cost_usd")),
+            });
+        }
+
+        let storage = sqlx::query("SELECT org_id, artifact_bytes, memory_bytes FROM billing_storage WHERE date = $1")
+            .bind(date.to_string())
+            .fetch_all(pool)
+            .await
+            .map_err(storage_error)?;
+        for row in storage {
+            let org_id: String = row.get("org_id");
+            let day = usage.entry(org_id.clone()).or_insert_with(|| DailyUsage::new(&org_id, date));
+            day.artifact_bytes = row.get::<i64, _>("artifact_bytes") as u64;
+            day.memory_bytes = row.get::<i64, _>("memory_bytes") as u64;
+        }
+
+        Ok(usage
+            .into_values()
+            .map(|mut day| {
+                day.prompt_tokens = day.by_model.iter().map(|model| model.prompt_tokens).sum();
+                day.completion_tokens = day.by_model.iter().map(|model| model.completion_tokens).sum();
+                day.cost_usd = usd(day.by_model.iter().map(|model| model.cost_usd).sum());
+                day
+            })
+            .collect())
+    }
+
+    /// The file each organization's usage on `date` stands at: the latest
+    /// delivered one if its content is unchanged, else an amendment of it,
+    /// or the day's first file
+    pub async fn files(&self, date: NaiveDate, now: DateTime<Utc>) -> Result<Vec<BillingFile>> {
+        let mut latest: HashMap<String, ExportedFile> = HashMap::new();
+        for file in self.manifest(None, Some(date), Some(date)).await? {
+            latest.insert(file.org_id.clone(), file);
+        }
+
+        Ok(self
+            .usage(date, now)
+            .await?
+            .into_iter()
+            .map(|usage| {
+                let content_hash = usage.content_hash();
+                let (revision, correction, supersedes) = match latest.get(&usage.org_id) {
+                    None => (1, false, None),
+                    Some(last) if last.content_hash == content_hash => {
+                        (last.revision, last.correction, last.supersedes.clone())
+                    }
+                    Some(last) => (last.revision + 1, true, Some(last.content_hash.clone())),
+                };
+                BillingFile { schema_version: SCHEMA_VERSION, revision, correction, supersedes, content_hash, usage }
+            })
+            .collect())
+    }
+
+    /// Deliver the files of `date` not delivered yet
+    async fn export_day(&self, date: NaiveDate, now: DateTime<Utc>, report: &mut ExportReport) -> Result<()> {
+        let delivered: std::collections::HashSet<(String, u32)> = self
+            .manifest(None, Some(date), Some(date))
+            .await?
+            .into_iter()
+            .map(|file| (file.org_id, file.revision))
+            .collect();
+
+        for file in self.files(date, now).await? {
+            if delivered.contains(&(file.usage.org_id.clone(), file.revision)) {
+                report.unchanged += 1;
+                continue;
+            }
+            let keys = match self.destination.deliver(&file).await {
+                Ok(keys) => keys,
+                Err(e) => {
+                    warn!("Failed to deliver billing file {}: {}", file.key("json"), e);
+                    report.failed.push(file.key("json"));
+                    continue;
+                }
+            };
+            let exported = ExportedFile {
+                org_id: file.usage.org_id.clone(),
+                date,
+                revision: file.revision,
+                correction: file.correction,
+                supersedes: file.supersedes.clone(),
+                content_hash: file.content_hash.clone(),
+                cost_usd: file.usage.cost_usd,
+                destination: self.destination.kind().to_string(),
+                keys,
+                delivered_at: Utc::now(),
+            };
+            self.record(&exported).await?;
+            if exported.correction {
+                info!("Delivered correction r{} of {}'s usage on {}", exported.revision, exported.org_id, date);
+            }
+            report.delivered.push(exported);
+        }
+        Ok(())
+    }
+
+    async fn record(&self, file: &ExportedFile) -> Result<()> {
+        let keys = serde_json::to_string(&file.keys)?;
+        let keys = keys.as_str();
+        self.pools
+            .write(|pool| async move {
+                sqlx::query(
+                    r#"
+                    INSERT INTO billing_exports (org_id, date, revision, correction, supersedes, content_hash,
+                                                 cost_usd, destination, keys, delivered_at)
+                    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+                    "#,
+                )
+                .bind(&file.org_id)
+                .bind(file.date.to_string())
+                .bind(file.revision as i64)
+                .bind(file.correction)
+                .bind(&file.supersedes)
+                .bind(&file.content_hash)
+                .bind(file.cost_usd)
+                .bind(&file.destination)
+                .bind(keys)
+                .bind(file.delivered_at.timestamp_millis())
+                .execute(&pool)
+                .await
+            })
+            .await
+            .map_err(storage_error)?;
+        Ok(())
+    }
+
+    /// Delivered files, of one organization if given, dated within
+    /// `[from, to]`, oldest date first and revisions in order
+    pub async fn manifest(&self, org_id: Option<&str>, from: Option<NaiveDate>, to: Option<NaiveDate>) -> Result<Vec<ExportedFile>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT org_id, date, revision, correction, supersedes, content_hash, cost_usd, destination, keys, delivered_at
+            FROM billing_exports
+            WHERE ($1 IS NULL OR org_id = $1) AND ($2 IS NULL OR date >= $2) AND ($3 IS NULL OR date <= $3)
+            ORDER BY date, org_id, revision
+            "#,
+        )
+        .bind(org_id)
+        .bind(from.map(|date| date.to_string()))
+        .bind(to.map(|date| date.to_string()))
+        .fetch_all(self.pools.reader())
+        .await
+        .map_err(storage_error)?;
+
+        rows.iter()
+            .map(|row| {
+                let date: String = row.get("date");
+                let keys: String = row.get("keys");
+                Ok(ExportedFile {
+                    org_id: row.get("org_id"),
+                    date: date.parse().map_err(|e| Error::Storage(format!("Invalid export date '{}': {}", date, e)))?,
+                    revision: row.get::<i64, _>("revision") as u32,
+                    correction: row.get("correction"),
+                    supersedes: row.get("supersedes"),
+                    content_hash: row.get("content_hash"),
+                    cost_usd: row.get("cost_usd"),
+                    destination: row.get("destination"),
+                    keys: serde_json::from_str(&keys)?,
+                    delivered_at: Utc.timestamp_millis_opt(row.get("delivered_at")).single().unwrap_or_default(),
+                })
+            })
+            .collect()
+    }
+
+    /// Export the finished days within the lookback window of `now`
+    pub async fn run(&self, now: DateTime<Utc>) -> ServerResult<ExportReport> {
+        let _running = self.running.lock().await;
+        let today = now.date_naive();
+        let mut report = ExportReport::default();
+        for back in (1..=self.config.lookback_days.max(1)).rev() {
+            self.export_day(today - Days::new(back as u64), now, &mut report).await.map_err(internal)?;
+        }
+        *self.last_run.lock() = Some(report.clone());
+        Ok(report)
+    }
+
+    /// Export one finished day, whether or not it is in the lookback window
+    pub async fn export(&self, date: NaiveDate, now: DateTime<Utc>) -> ServerResult<ExportReport> {
+        if date >= now.date_naive() {
+            return Err(ServerError::InvalidInput(format!("{} has not finished yet", date)));
+        }
+        let _running = self.running.lock().await;
+        let mut report = ExportReport::default();
+        self.export_day(date, now, &mut report).await.map_err(internal)?;
+        Ok(report)
+    }
+}
+
+fn internal(e: Error) -> ServerError {
+    ServerError::Internal(e.to_string())
+}
+
+/// Export every `interval_secs`
+pub async fn export_task(exporter: Arc<BillingExporter>) {
+    let mut interval = tokio::time::interval(Duration::from_secs(exporter.config.interval_secs.max(1)));
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+    loop {
+        interval.tick().await;
+        match exporter.run(Utc::now()).await {
+            Ok(report) if report.delivered.is_empty() && report.failed.is_empty() => {
+                debug!("Billing export found nothing new to deliver")
+            }
+            Ok(report) => {
+                info!(
+                    "Billing export delivered {} files, {} unchanged, {} failed",
+                    report.delivered.len(),
+                    report.unchanged,
+                    report.failed.len()
+                );
+            }
+            Err(e) => error!("Billing export failed: {}", e),
+        }
+    }
+}
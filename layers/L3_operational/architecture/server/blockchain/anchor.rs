@@ -0,0 +1,50 @@
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use ethers::core::types::{Bytes, TransactionRequest};
+
+use hal9_core::receipt::ChainReceipt;
+
+use super::chain::{BlockchainClient, ChainConfig};
+use crate::receipts::Anchor;
+
+// ============ Receipt Anchor ============
+
+/// Anchors receipt roots by sending a zero-value transaction to the
+/// server's own address with the root as calldata
+pub struct EthereumAnchor {
+    client: BlockchainClient,
+}
+
+impl EthereumAnchor {
+    pub async fn connect(rpc_url: &str, chain_id: u64, private_key: &str) -> Result<Self> {
+        let config = ChainConfig {
+            rpc_url: rpc_url.to_string(),
+            chain_id,
+            private_key: Some(private_key.to_string()),
+            ..Default::default()
+        };
+        let client = BlockchainClient::new(config).await?;
+        if !client.verify_network().await? {
+            anyhow::bail!("RPC endpoint {} is not on chain {}", rpc_url, chain_id);
+        }
+        Ok(Self { client })
+    }
+}
+
+#[async_trait]
+impl Anchor for EthereumAnchor {
+    fn name(&self) -> &str {
+        "ethereum"
+    }
+
+    async fn anchor(&self, receipt: &ChainReceipt) -> Result<Option<String>> {
+        let address = self.client.get_address().context("No wallet configured")?;
+        let root = receipt.root_bytes()?;
+        let tx = TransactionRequest::new()
+            .to(address)
+            .value(0)
+            .data(Bytes::from(root));
+        let tx_hash = self.client.send_transaction(tx).await?;
+        Ok(Some(tx_hash))
+    }
+}
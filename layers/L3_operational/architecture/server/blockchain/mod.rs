@@ -1,3 +1,4 @@
+pub mod anchor;
 pub mod chain;
 pub mod consensus;
 pub mod contracts;
@@ -6,6 +7,7 @@ pub mod proof;
 pub mod storage;
 pub mod service;
 
+pub use anchor::EthereumAnchor;
 pub use chain::{BlockchainClient, ChainConfig, Network};
 pub use consensus::{ConsensusEngine, ConsensusProtocol};
 pub use contracts::{NeuronContract, IncentiveContract};
@@ -0,0 +1,346 @@
+//! Recurring cost budgets: period boundaries, carryover and persistence
+
+use std::path::Path;
+
+use chrono::{DateTime, Datelike, Duration, FixedOffset, NaiveDate, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+use hal9_core::{Result, Error, config::{BudgetPeriod, BudgetPeriodConfig}};
+
+pub use hal9_api_types::PeriodStatus;
+
+/// Parse a timezone given as "UTC" or a fixed offset like "+09:00"
+pub fn parse_offset(timezone: &str) -> Result<FixedOffset> {
+    let tz = timezone.trim();
+    if tz.is_empty() || tz.eq_ignore_ascii_case("utc") || tz == "Z" {
+        return Ok(FixedOffset::east_opt(0).unwrap());
+    }
+
+    let invalid = || Error::Config(format!("Invalid budget timezone '{}': expected UTC or ±HH:MM", timezone));
+    let (sign, rest) = match tz.as_bytes()[0] {
+        b'+' => (1, &tz[1..]),
+        b'-' => (-1, &tz[1..]),
+        _ => return Err(invalid()),
+    };
+    let (hours, minutes) = rest.split_once(':').unwrap_or((rest, "0"));
+    let hours: i32 = hours.parse().map_err(|_| invalid())?;
+    let minutes: i32 = minutes.parse().map_err(|_| invalid())?;
+    if hours > 14 || minutes >= 60 {
+        return Err(invalid());
+    }
+
+    FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60)).ok_or_else(invalid)
+}
+
+/// Start of the period containing `now`
+pub fn period_start(period: BudgetPeriod, offset: FixedOffset, now: DateTime<Utc>) -> DateTime<Utc> {
+    let today = now.with_timezone(&offset).date_naive();
+    let first_day = match period {
+        BudgetPeriod::Daily => today,
+        BudgetPeriod::Weekly => today - Duration::days(today.weekday().num_days_from_monday() as i64),
+        BudgetPeriod::Monthly => today.with_day(1).unwrap(),
+    };
+    local_midnight(offset, first_day)
+}
+
+/// Start of the period following the one beginning at `start`
+pub fn next_reset(period: BudgetPeriod, offset: FixedOffset, start: DateTime<Utc>) -> DateTime<Utc> {
+    let first_day = start.with_timezone(&offset).date_naive();
+    let next_day = match period {
+        BudgetPeriod::Daily => first_day + Duration::days(1),
+        BudgetPeriod::Weekly => first_day + Duration::days(7),
+        BudgetPeriod::Monthly => {
+            let (year, month) = if first_day.month() == 12 {
+                (first_day.year() + 1, 1)
+            } else {
+                (first_day.year(), first_day.month() + 1)
+            };
+            NaiveDate::from_ymd_opt(year, month, 1).unwrap()
+        }
+    };
+    local_midnight(offset, next_day)
+}
+
+/// The API's name for a configured period
+fn wire_period(period: BudgetPeriod) -> hal9_api_types::BudgetPeriod {
+    match period {
+        BudgetPeriod::Daily => hal9_api_types::BudgetPeriod::Daily,
+        BudgetPeriod::Weekly => hal9_api_types::BudgetPeriod::Weekly,
+        BudgetPeriod::Monthly => hal9_api_types::BudgetPeriod::Monthly,
+    }
+}
+
+fn local_midnight(offset: FixedOffset, date: NaiveDate) -> DateTime<Utc> {
+    offset.from_local_datetime(&date.and_hms_opt(0, 0, 0).unwrap())
+        .unwrap()
+        .with_timezone(&Utc)
+}
+
+/// Period accounting persisted across restarts
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PeriodState {
+    pub period_start: DateTime<Utc>,
+    pub consumed: f64,
+    pub carried_over: f64,
+}
+
+/// A budget that resets every period.
+///
+/// Periods only move forward: a clock stepping backwards keeps the current
+/// period (and its spend) until time passes its end again, so skew can
+/// never reopen an earlier period or grant a fresh budget.
+pub struct PeriodBudget {
+    config: BudgetPeriodConfig,
+    offset: FixedOffset,
+    state: PeriodState,
+}
+
+impl PeriodBudget {
+    /// Start a fresh period containing `now`
+    pub fn new(config: BudgetPeriodConfig, now: DateTime<Utc>) -> Result<Self> {
+        let offset = parse_offset(&config.timezone)?;
+        let state = PeriodState {
+            period_start: period_start(config.period, offset, now),
+            consumed: 0.0,
+            carried_over: 0.0,
+        };
+        Ok(Self { config, offset, state })
+    }
+
+    /// Resume from persisted state, rolling forward to `now`
+    pub fn restore(config: BudgetPeriodConfig, mut state: PeriodState, now: DateTime<Utc>) -> Result<Self> {
+        let offset = parse_offset(&config.timezone)?;
+
+        // The period or timezone may have changed since the state was saved
+        let aligned = period_start(config.period, offset, state.period_start);
+        if aligned != state.period_start {
+            info!("Budget period settings changed; realigning period start to {}", aligned);
+            state.period_start = aligned;
+        }
+
+        let mut budget = Self { config, offset, state };
+        budget.advance(now);
+        Ok(budget)
+    }
+
+    /// Load state from `config.state_path`, starting fresh if there is none
+    pub fn load(config: BudgetPeriodConfig, now: DateTime<Utc>) -> Result<Self> {
+        let path = Path::new(&config.state_path);
+        if !path.exists() {
+            return Self::new(config, now);
+        }
+
+        let state = std::fs::read_to_string(path)
+            .map_err(Error::from)
+            .and_then(|data| serde_json::from_str::<PeriodState>(&data).map_err(Error::from));
+        match state {
+            Ok(state) => Self::restore(config, state, now),
+            Err(e) => {
+                warn!("Ignoring unreadable budget state {}: {}", config.state_path, e);
+                Self::new(config, now)
+            }
+        }
+    }
+
+    /// Persist the current state
+    pub fn save(&self) -> Result<()> {
+        let path = Path::new(&self.config.state_path);
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+
+        // Write beside the target and rename so a crash never leaves a torn file
+        let tmp = path.with_extension("tmp");
+        std::fs::write(&tmp, serde_json::to_vec_pretty(&self.state)?)?;
+        std::fs::rename(&tmp, path)?;
+        Ok(())
+    }
+
+    /// Roll into the period containing `now`, carrying unused budget over.
+    /// Returns whether any reset happened.
+    pub fn advance(&mut self, now: DateTime<Utc>) -> bool {
+        let mut rolled = false;
+
+        loop {
+            let reset = next_reset(self.config.period, self.offset, self.state.period_start);
+            if now < reset {
+                break;
+            }
+
+            // Each elapsed period hands its leftover to the next, capped
+            let unused = (self.available() - self.state.consumed).max(0.0);
+            self.state.carried_over = unused.min(self.config.max_carryover.max(0.0));
+            self.state.consumed = 0.0;
+            self.state.period_start = reset;
+            rolled = true;
+        }
+
+        if rolled {
+            info!(
+                "Budget period reset at {} (carried over ${:.2})",
+                self.state.period_start, self.state.carried_over
+            );
+        }
+        rolled
+    }
+
+    pub fn record(&mut self, cost: f64) {
+        self.state.consumed += cost;
+    }
+
+    /// Budget for the current period including carryover
+    pub fn available(&self) -> f64 {
+        self.config.budget + self.state.carried_over
+    }
+
+    pub fn remaining(&self) -> f64 {
+        (self.available() - self.state.consumed).max(0.0)
+    }
+
+    pub fn is_exhausted(&self) -> bool {
+        self.state.consumed >= self.available()
+    }
+
+    /// Fallback model to use right now, if the budget is spent and grace
+    /// mode is configured
+    pub fn downgrade_model(&self) -> Option<&str> {
+        if self.is_exhausted() {
+            self.config.fallback_model.as_deref()
+        } else {
+            None
+        }
+    }
+
+    pub fn config(&self) -> &BudgetPeriodConfig {
+        &self.config
+    }
+
+    pub fn state(&self) -> &PeriodState {
+        &self.state
+    }
+
+    pub fn status(&self) -> PeriodStatus {
+        PeriodStatus {
+            period: wire_period(self.config.period),
+            period_start: self.state.period_start,
+            resets_at: next_reset(self.config.period, self.offset, self.state.period_start),
+            budget: self.config.budget,
+            carried_over: self.state.carried_over,
+            consumed: self.state.consumed,
+            remaining: self.remaining(),
+            downgraded_to: self.downgrade_model().map(str::to_string),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(period: BudgetPeriod, timezone: &str, max_carryover: f64) -> BudgetPeriodConfig {
+        BudgetPeriodConfig {
+            period,
+            budget: 10.0,
+            timezone: timezone.to_string(),
+            max_carryover,
+            fallback_model: None,
+            state_path: String::new(),
+        }
+    }
+
+    fn at(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    #[test]
+    fn test_parse_offset() {
+        assert_eq!(parse_offset("UTC").unwrap().local_minus_utc(), 0);
+        assert_eq!(parse_offset("+09:00").unwrap().local_minus_utc(), 9 * 3600);
+        assert_eq!(parse_offset("-05:30").unwrap().local_minus_utc(), -(5 * 3600 + 1800));
+        assert!(parse_offset("Asia/Seoul").is_err());
+        assert!(parse_offset("+25:00").is_err());
+    }
+
+    #[test]
+    fn test_period_boundaries_follow_timezone() {
+        let seoul = parse_offset("+09:00").unwrap();
+        // 2026-03-31 16:00 UTC is already April 1st in Seoul
+        let now = at("2026-03-31T16:00:00Z");
+
+        assert_eq!(period_start(BudgetPeriod::Monthly, seoul, now), at("2026-03-31T15:00:00Z"));
+        assert_eq!(period_start(BudgetPeriod::Daily, seoul, now), at("2026-03-31T15:00:00Z"));
+        // April 1st 2026 is a Wednesday; the week began Monday March 30th
+        assert_eq!(period_start(BudgetPeriod::Weekly, seoul, now), at("2026-03-29T15:00:00Z"));
+
+        let utc = parse_offset("UTC").unwrap();
+        assert_eq!(next_reset(BudgetPeriod::Monthly, utc, at("2026-12-01T00:00:00Z")), at("2027-01-01T00:00:00Z"));
+    }
+
+    #[test]
+    fn test_reset_at_boundary() {
+        let mut budget = PeriodBudget::new(config(BudgetPeriod::Daily, "UTC", 0.0), at("2026-05-01T10:00:00Z")).unwrap();
+        budget.record(10.0);
+        assert!(budget.is_exhausted());
+
+        // One second before midnight nothing changes
+        assert!(!budget.advance(at("2026-05-01T23:59:59Z")));
+        assert!(budget.is_exhausted());
+
+        // At midnight the period resets
+        assert!(budget.advance(at("2026-05-02T00:00:00Z")));
+        assert_eq!(budget.state().consumed, 0.0);
+        assert_eq!(budget.state().period_start, at("2026-05-02T00:00:00Z"));
+        assert_eq!(budget.remaining(), 10.0);
+    }
+
+    #[test]
+    fn test_carryover_is_capped() {
+        let mut budget = PeriodBudget::new(config(BudgetPeriod::Monthly, "UTC", 4.0), at("2026-05-10T00:00:00Z")).unwrap();
+
+        // $3 unused carries over in full
+        budget.record(7.0);
+        budget.advance(at("2026-06-01T00:00:00Z"));
+        assert_eq!(budget.state().carried_over, 3.0);
+        assert_eq!(budget.available(), 13.0);
+
+        // $13 unused is capped at $4
+        budget.advance(at("2026-07-01T00:00:00Z"));
+        assert_eq!(budget.state().carried_over, 4.0);
+
+        // Overspending leaves nothing to carry
+        budget.record(20.0);
+        budget.advance(at("2026-08-01T00:00:00Z"));
+        assert_eq!(budget.state().carried_over, 0.0);
+        assert_eq!(budget.available(), 10.0);
+    }
+
+    #[test]
+    fn test_clock_stepping_backwards_keeps_period() {
+        let mut budget = PeriodBudget::new(config(BudgetPeriod::Daily, "UTC", 0.0), at("2026-05-02T08:00:00Z")).unwrap();
+        budget.record(6.0);
+
+        assert!(!budget.advance(at("2026-05-01T23:00:00Z")));
+        assert_eq!(budget.state().period_start, at("2026-05-02T00:00:00Z"));
+        assert_eq!(budget.state().consumed, 6.0);
+    }
+
+    #[test]
+    fn test_state_survives_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut cfg = config(BudgetPeriod::Weekly, "UTC", 5.0);
+        cfg.state_path = dir.path().join("budget.json").to_string_lossy().into_owned();
+
+        let mut budget = PeriodBudget::load(cfg.clone(), at("2026-05-05T12:00:00Z")).unwrap();
+        budget.record(8.0);
+        budget.save().unwrap();
+
+        // Same week: spend is remembered
+        let reloaded = PeriodBudget::load(cfg.clone(), at("2026-05-06T12:00:00Z")).unwrap();
+        assert_eq!(reloaded.state().consumed, 8.0);
+
+        // Next week: reset with $2 carried over
+        let next_week = PeriodBudget::load(cfg, at("2026-05-12T12:00:00Z")).unwrap();
+        assert_eq!(next_week.state().consumed, 0.0);
+        assert_eq!(next_week.state().carried_over, 2.0);
+    }
+}
@@ -0,0 +1,396 @@
+//! Chain comparison
+//!
+//! Lines up the signal trees of two chains, typically a chain and its replay,
+//! and reports how each signal changed. Trees are aligned top-down: the roots
+//! are paired, then the children of every paired signal are paired within
+//! each layer. Siblings pair up by neuron and output similarity rather than
+//! by arrival order, so reordered siblings still match; siblings left over on
+//! either side are reported, with everything beneath them, as added or
+//! removed.
+
+use std::collections::{HashMap, HashSet};
+
+use serde::Serialize;
+
+use crate::chain_tracker::{ChainRecord, ChainStep};
+use crate::claude::model_pricing;
+
+/// Lowest similarity at which two siblings in the same layer are taken to be
+/// the same signal
+const MATCH_THRESHOLD: f64 = 0.3;
+
+/// Lines of unchanged content around each change in a content diff
+const DIFF_CONTEXT: usize = 3;
+
+/// How a signal changed between the chains
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NodeChange {
+    /// Only in the second chain
+    Added,
+    /// Only in the first chain
+    Removed,
+    /// In both, with different content, tokens or model
+    Changed,
+    Unchanged,
+}
+
+/// A signal as processed in one of the chains
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct NodeSide {
+    pub signal_id: String,
+    pub neuron_id: String,
+    pub model: Option<String>,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub duration_ms: i64,
+    pub error: Option<String>,
+}
+
+impl NodeSide {
+    fn from_step(step: &ChainStep) -> Self {
+        Self {
+            signal_id: step.signal_id.clone(),
+            neuron_id: step.neuron_id.clone(),
+            model: step.model.clone(),
+            prompt_tokens: step.prompt_tokens,
+            completion_tokens: step.completion_tokens,
+            duration_ms: step.duration_ms,
+            error: step.error.clone(),
+        }
+    }
+
+    fn tokens(&self) -> u64 {
+        self.prompt_tokens + self.completion_tokens
+    }
+}
+
+/// One aligned position in the two trees
+#[derive(Debug, Clone, Serialize)]
+pub struct NodeDiff {
+    /// Position in the tree as layer and index among same-layer siblings,
+    /// e.g. `L4[0]/L3[1]`
+    pub path: String,
+    pub layer: String,
+    pub change: NodeChange,
+    pub a: Option<NodeSide>,
+    pub b: Option<NodeSide>,
+    /// Unified diff of the outputs; `None` when they are equal
+    pub content_diff: Option<String>,
+    /// Tokens in the second chain minus tokens in the first
+    pub tokens_delta: i64,
+    pub latency_delta_ms: i64,
+}
+
+/// Totals over a comparison
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ComparisonSummary {
+    pub nodes_added: usize,
+    pub nodes_removed: usize,
+    pub nodes_changed: usize,
+    pub nodes_unchanged: usize,
+    pub tokens_delta: i64,
+    /// Estimated Claude cost of each chain, in dollars
+    pub cost_a: f64,
+    pub cost_b: f64,
+    pub cost_delta: f64,
+    /// Difference in total chain duration, when both chains finished
+    pub duration_delta_ms: Option<i64>,
+}
+
+/// Node-by-node comparison of two chains
+#[derive(Debug, Clone, Serialize)]
+pub struct ChainComparison {
+    pub a: String,
+    pub b: String,
+    pub nodes: Vec<NodeDiff>,
+    pub summary: ComparisonSummary,
+}
+
+impl ChainComparison {
+    /// Compare two tracked chains. Steps without a recorded model are
+    /// priced as `default_model`.
+    pub fn from_records(a: &ChainRecord, b: &ChainRecord, default_model: &str) -> Self {
+        let mut comparison = Self::from_steps(&a.chain_id, &a.steps, &b.chain_id, &b.steps, default_model);
+        let duration = |record: &ChainRecord| record.completed_at.map(|done| (done - record.created_at).num_milliseconds());
+        comparison.summary.duration_delta_ms = duration(a).zip(duration(b)).map(|(a, b)| b - a);
+        comparison
+    }
+
+    /// Compare two chains by their steps
+    pub fn from_steps(a_id: &str, a: &[ChainStep], b_id: &str, b: &[ChainStep], default_model: &str) -> Self {
+        let (a_tree, b_tree) = (Tree::new(a), Tree::new(b));
+        let mut comparison = Self {
+            a: a_id.to_string(),
+            b: b_id.to_string(),
+            nodes: Vec::new(),
+            summary: ComparisonSummary::default(),
+        };
+        comparison.walk(&a_tree, &a_tree.roots, &b_tree, &b_tree.roots, "");
+
+        let cost = |steps: &[ChainStep]| steps.iter().map(|step| step_cost(step, default_model)).sum::<f64>();
+        let summary = &mut comparison.summary;
+        summary.cost_a = cost(a);
+        summary.cost_b = cost(b);
+        summary.cost_delta = summary.cost_b - summary.cost_a;
+        for node in &comparison.nodes {
+            match node.change {
+                NodeChange::Added => summary.nodes_added += 1,
+                NodeChange::Removed => summary.nodes_removed += 1,
+                NodeChange::Changed => summary.nodes_changed += 1,
+                NodeChange::Unchanged => summary.nodes_unchanged += 1,
+            }
+            summary.tokens_delta += node.tokens_delta;
+        }
+        comparison
+    }
+
+    /// Align two sibling lists and record them and their subtrees, in
+    /// pre-order
+    fn walk(&mut self, a_tree: &Tree, a: &[usize], b_tree: &Tree, b: &[usize], prefix: &str) {
+        let mut positions: HashMap<&str, usize> = HashMap::new();
+        for (a_index, b_index) in align(a_tree.steps, a, b_tree.steps, b) {
+            let step = b_index.map(|i| &b_tree.steps[i]).or(a_index.map(|i| &a_tree.steps[i])).unwrap();
+            let position = positions.entry(step.layer.as_str()).or_default();
+            let path = format!("{}{}[{}]", prefix, step.layer, position);
+            *position += 1;
+
+            self.nodes.push(diff_node(&path, a_index.map(|i| &a_tree.steps[i]), b_index.map(|i| &b_tree.steps[i])));
+            let children = |tree: &Tree, index: Option<usize>| index.map(|i| tree.children[i].clone()).unwrap_or_default();
+            self.walk(a_tree, &children(a_tree, a_index), b_tree, &children(b_tree, b_index), &format!("{}/", path));
+        }
+    }
+}
+
+/// Steps of a chain as a forest
+struct Tree<'a> {
+    steps: &'a [ChainStep],
+    roots: Vec<usize>,
+    /// Children of each step, in the order they were processed
+    children: Vec<Vec<usize>>,
+}
+
+impl<'a> Tree<'a> {
+    /// Steps whose parent was not recorded start trees of their own
+    fn new(steps: &'a [ChainStep]) -> Self {
+        let index: HashMap<&str, usize> = steps.iter().enumerate().map(|(i, step)| (step.signal_id.as_str(), i)).collect();
+        let mut tree = Self { steps, roots: Vec::new(), children: vec![Vec::new(); steps.len()] };
+        for (i, step) in steps.iter().enumerate() {
+            match step.parent_id.as_deref().and_then(|parent| index.get(parent)).filter(|parent| **parent != i) {
+                Some(parent) => tree.children[*parent].push(i),
+                None => tree.roots.push(i),
+            }
+        }
+
+        // A parent cycle leaves steps unreachable from any root; promote one
+        // step of each such cycle to a root
+        let mut reached = HashSet::new();
+        let mut stack = tree.roots.clone();
+        for i in 0..steps.len() {
+            while let Some(node) = stack.pop() {
+                if reached.insert(node) {
+                    stack.extend(&tree.children[node]);
+                }
+            }
+            if !reached.contains(&i) {
+                tree.roots.push(i);
+                stack.push(i);
+            }
+        }
+        for children in &mut tree.children {
+            children.retain(|child| !tree.roots.contains(child));
+        }
+        tree
+    }
+}
+
+/// Pair up siblings in the same layer, best match first. Returns every
+/// sibling once, paired or not, ordered by position in `b` with siblings
+/// only in `a` after the sibling they followed there.
+fn align(a_steps: &[ChainStep], a: &[usize], b_steps: &[ChainStep], b: &[usize]) -> Vec<(Option<usize>, Option<usize>)> {
+    let mut candidates = Vec::new();
+    for (ai, &a_index) in a.iter().enumerate() {
+        for (bi, &b_index) in b.iter().enumerate() {
+            let (a_step, b_step) = (&a_steps[a_index], &b_steps[b_index]);
+            if a_step.layer != b_step.layer {
+                continue;
+            }
+            let mut score = 0.5 * similarity(&content(a_step), &content(b_step));
+            if a_step.neuron_id == b_step.neuron_id {
+                score += 0.5;
+            }
+            // Between equal candidates, prefer the same position
+            let offset = (ai as f64 - bi as f64).abs() / (a.len().max(b.len()) as f64);
+            candidates.push((score - 0.01 * offset, ai, bi));
+        }
+    }
+    candidates.sort_by(|x, y| y.0.total_cmp(&x.0).then((x.1, x.2).cmp(&(y.1, y.2))));
+
+    let mut a_match = vec![None; a.len()];
+    let mut b_match = vec![None; b.len()];
+    for (score, ai, bi) in candidates {
+        if score >= MATCH_THRESHOLD && a_match[ai].is_none() && b_match[bi].is_none() {
+            a_match[ai] = Some(bi);
+            b_match[bi] = Some(ai);
+        }
+    }
+
+    // Unmatched `a` siblings go after the closest earlier sibling that has a
+    // place in `b`
+    let mut pairs: Vec<(Option<usize>, Option<usize>)> = Vec::new();
+    let mut removed_after: Vec<Vec<usize>> = vec![Vec::new(); b.len() + 1];
+    let mut anchor = 0;
+    for (ai, matched) in a_match.iter().enumerate() {
+        match matched {
+            Some(bi) => anchor = bi + 1,
+            None => removed_after[anchor].push(ai),
+        }
+    }
+    for ai in &removed_after[0] {
+        pairs.push((Some(a[*ai]), None));
+    }
+    for (bi, matched) in b_match.iter().enumerate() {
+        pairs.push((matched.map(|ai| a[ai]), Some(b[bi])));
+        for ai in &removed_after[bi + 1] {
+            pairs.push((Some(a[*ai]), None));
+        }
+    }
+    pairs
+}
+
+fn diff_node(path: &str, a: Option<&ChainStep>, b: Option<&ChainStep>) -> NodeDiff {
+    let layer = b.or(a).map(|step| step.layer.clone()).unwrap_or_default();
+    let (a_content, b_content) = (a.map(content).unwrap_or_default(), b.map(content).unwrap_or_default());
+    let content_diff = (a_content != b_content).then(|| {
+        let label = |step: Option<&ChainStep>, side: &str| match step {
+            Some(step) => format!("{}/{}", side, step.signal_id),
+            None => "/dev/null".to_string(),
+        };
+        unified_diff(&a_content, &b_content, &label(a, "a"), &label(b, "b"))
+    });
+
+    let (a, b) = (a.map(NodeSide::from_step), b.map(NodeSide::from_step));
+    let tokens = |side: &Option<NodeSide>| side.as_ref().map_or(0, |side| side.tokens() as i64);
+    let latency = |side: &Option<NodeSide>| side.as_ref().map_or(0, |side| side.duration_ms);
+    let change = match (&a, &b) {
+        (None, _) => NodeChange::Added,
+        (_, None) => NodeChange::Removed,
+        (Some(x), Some(y)) if content_diff.is_some() || x.tokens() != y.tokens() || x.model != y.model => NodeChange::Changed,
+        _ => NodeChange::Unchanged,
+    };
+
+    NodeDiff {
+        path: path.to_string(),
+        layer,
+        change,
+        tokens_delta: tokens(&b) - tokens(&a),
+        latency_delta_ms: latency(&b) - latency(&a),
+        content_diff,
+        a,
+        b,
+    }
+}
+
+/// What a step produced: its output, or its error
+fn content(step: &ChainStep) -> String {
+    match (&step.output, &step.error) {
+        (Some(output), _) => output.clone(),
+        (None, Some(error)) => format!("error: {}", error),
+        (None, None) => String::new(),
+    }
+}
+
+/// Share of distinct words the two texts have in common, 1.0 when both are
+/// empty
+fn similarity(a: &str, b: &str) -> f64 {
+    let a: HashSet<&str> = a.split_whitespace().collect();
+    let b: HashSet<&str> = b.split_whitespace().collect();
+    if a.is_empty() && b.is_empty() {
+        return 1.0;
+    }
+    a.intersection(&b).count() as f64 / a.union(&b).count() as f64
+}
+
+/// Estimated Claude cost of a step in dollars
+pub(crate) fn step_cost(step: &ChainStep, default_model: &str) -> f64 {
+    let (prompt, completion) = model_pricing(step.model.as_deref().unwrap_or(default_model));
+    (step.prompt_tokens as f64 * prompt + step.completion_tokens as f64 * completion) / 1000.0
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Edit {
+    Keep(usize, usize),
+    Delete(usize),
+    Insert(usize),
+}
+
+/// Line-based unified diff of `old` and `new`, with `DIFF_CONTEXT` lines of
+/// context around each hunk
+pub fn unified_diff(old: &str, new: &str, old_label: &str, new_label: &str) -> String {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+
+    // Longest common subsequence of lines, from the end
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] { lcs[i + 1][j + 1] + 1 } else { lcs[i + 1][j].max(lcs[i][j + 1]) };
+        }
+    }
+    let mut edits = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            edits.push(Edit::Keep(i, j));
+            i += 1;
+            j += 1;
+        } else if i < old.len() && (j == new.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            edits.push(Edit::Delete(i));
+            i += 1;
+        } else {
+            edits.push(Edit::Insert(j));
+            j += 1;
+        }
+    }
+
+    let mut out = format!("--- {}\n+++ {}\n", old_label, new_label);
+    let changed: Vec<usize> = edits.iter().enumerate().filter(|(_, e)| !matches!(e, Edit::Keep(..))).map(|(k, _)| k).collect();
+    let mut k = 0;
+    while k < changed.len() {
+        // Extend the hunk while the next change is within reach of its context
+        let start = changed[k].saturating_sub(DIFF_CONTEXT);
+        let mut last = changed[k];
+        while k + 1 < changed.len() && changed[k + 1] <= last + 2 * DIFF_CONTEXT {
+            k += 1;
+            last = changed[k];
+        }
+        let end = (last + DIFF_CONTEXT + 1).min(edits.len());
+        k += 1;
+
+        let hunk = &edits[start..end];
+        // Line numbers where the hunk starts on each side, counted from 1
+        let (mut old_start, mut new_start) = (0, 0);
+        for edit in &edits[..start] {
+            match edit {
+                Edit::Keep(..) => {
+                    old_start += 1;
+                    new_start += 1;
+                }
+                Edit::Delete(_) => old_start += 1,
+                Edit::Insert(_) => new_start += 1,
+            }
+        }
+        let old_count = hunk.iter().filter(|e| !matches!(e, Edit::Insert(_))).count();
+        let new_count = hunk.iter().filter(|e| !matches!(e, Edit::Delete(_))).count();
+        let range = |start: usize, count: usize| if count == 0 { format!("{},0", start) } else { format!("{},{}", start + 1, count) };
+        out.push_str(&format!("@@ -{} +{} @@\n", range(old_start, old_count), range(new_start, new_count)));
+        for edit in hunk {
+            match *edit {
+                Edit::Keep(i, _) => out.push_str(&format!(" {}\n", old[i])),
+                Edit::Delete(i) => out.push_str(&format!("-{}\n", old[i])),
+                Edit::Insert(j) => out.push_str(&format!("+{}\n", new[j])),
+            }
+        }
+    }
+    out
+}
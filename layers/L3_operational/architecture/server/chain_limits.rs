@@ -0,0 +1,208 @@
+//! Per-user concurrent chain limits
+//!
+//! Limits are resolved per user from, in order of precedence, the user's
+//! organization override (enterprise settings or config), their role's limit,
+//! and the configured default. Active chains are counted against the chain
+//! tracker so finished chains free their slot without explicit release.
+
+use dashmap::DashMap;
+use parking_lot::RwLock;
+use serde::Serialize;
+use std::sync::Arc;
+
+use hal9_core::config::ChainLimitsConfig;
+use crate::chain_tracker::{ChainStatus, ChainTracker};
+
+/// Identity a chain is charged to
+#[derive(Debug, Clone)]
+pub struct ChainOwner {
+    pub user_id: String,
+    pub role: String,
+    pub org_id: Option<String>,
+    /// API key the chain is started with, charged for its tokens
+    pub api_key_id: Option<String>,
+}
+
+impl From<&crate::auth_middleware::AuthUser> for ChainOwner {
+    fn from(user: &crate::auth_middleware::AuthUser) -> Self {
+        Self {
+            user_id: user.user_id.clone(),
+            role: user.role.clone(),
+            org_id: user.org_id.clone(),
+            api_key_id: user.api_key_id.clone(),
+        }
+    }
+}
+
+/// Current usage against a user's limit
+#[derive(Debug, Clone, Serialize)]
+pub struct LimitUsage {
+    pub user_id: String,
+    pub role: String,
+    pub org_id: Option<String>,
+    pub active_chains: usize,
+    pub max_concurrent_chains: u32,
+    pub active_signals: usize,
+    pub enforced: bool,
+}
+
+/// Enforces concurrent chain limits at submission time
+pub struct ChainLimiter {
+    config: RwLock<ChainLimitsConfig>,
+    org_limits: DashMap<String, u32>,
+    active: DashMap<String, Vec<String>>,
+    chain_tracker: Arc<ChainTracker>,
+}
+
+impl ChainLimiter {
+    /// Create a new limiter counting chains in `chain_tracker`
+    pub fn new(config: ChainLimitsConfig, chain_tracker: Arc<ChainTracker>) -> Self {
+        let org_limits = config.org_limits.iter()
+            .map(|(org, limit)| (org.clone(), *limit))
+            .collect();
+
+        Self {
+            config: RwLock::new(config),
+            org_limits,
+            active: DashMap::new(),
+            chain_tracker,
+        }
+    }
+
+    /// Override the limit for an organization (from its enterprise settings);
+    /// `None` removes the override
+    pub fn set_org_limit(&self, org_id: &str, limit: Option<u32>) {
+        match limit {
+            Some(limit) => {
+                self.org_limits.insert(org_id.to_string(), limit);
+            }
+            None => {
+                self.org_limits.remove(org_id);
+            }
+        }
+    }
+
+    /// Apply changed role and default limits to chains admitted from now
+    /// on. Organization limits are kept, as they may have been set at
+    /// runtime.
+    pub fn set_config(&self, config: ChainLimitsConfig) {
+        *self.config.write() = config;
+    }
+
+    /// Resolve the concurrent chain limit for an owner
+    pub fn limit_for(&self, owner: &ChainOwner) -> u32 {
+        if let Some(limit) = owner.org_id.as_ref().and_then(|org| self.org_limits.get(org)) {
+            return *limit;
+        }
+        let config = self.config.read();
+        config.role_limits.get(&owner.role)
+            .copied()
+            .unwrap_or(config.default_max_concurrent_chains)
+    }
+
+    /// Admit a new chain for `owner`, or explain why it was refused
+    pub fn admit(&self, owner: &ChainOwner, chain_id: &str) -> std::result::Result<(), String> {
+        let limit = self.limit_for(owner);
+        let mut chains = self.active.entry(owner.user_id.clone()).or_default();
+        chains.retain(|id| self.is_running(id));
+
+        if self.config.read().enabled && chains.len() >= limit as usize {
+            return Err(format!(
+                "Concurrent chain limit reached ({} of {})",
+                chains.len(),
+                limit
+            ));
+        }
+
+        chains.push(chain_id.to_string());
+        Ok(())
+    }
+
+    /// Number of running chains charged to a user
+    pub fn active_chains(&self, user_id: &str) -> usize {
+        self.active.get(user_id)
+            .map(|chains| chains.iter().filter(|id| self.is_running(id)).count())
+            .unwrap_or(0)
+    }
+
+    /// Usage against limit for an owner
+    pub fn usage(&self, owner: &ChainOwner, active_signals: usize) -> LimitUsage {
+        LimitUsage {
+            user_id: owner.user_id.clone(),
+            role: owner.role.clone(),
+            org_id: owner.org_id.clone(),
+            active_chains: self.active_chains(&owner.user_id),
+            max_concurrent_chains: self.limit_for(owner),
+            active_signals,
+            enforced: self.config.read().enabled,
+        }
+    }
+
+    fn is_running(&self, chain_id: &str) -> bool {
+        self.chain_tracker.get(chain_id)
+            .map(|record| record.status == ChainStatus::Running)
+            .unwrap_or(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hal9_core::NeuronSignal;
+
+    fn owner(role: &str, org: Option<&str>) -> ChainOwner {
+        ChainOwner {
+            user_id: "u1".to_string(),
+            role: role.to_string(),
+            org_id: org.map(|s| s.to_string()),
+            api_key_id: None,
+        }
+    }
+
+    #[test]
+    fn test_limit_resolution_order() {
+        let mut config = ChainLimitsConfig::default();
+        config.org_limits.insert("acme".to_string(), 42);
+        let limiter = ChainLimiter::new(config, Arc::new(ChainTracker::new()));
+
+        assert_eq!(limiter.limit_for(&owner("guest", None)), 1);
+        assert_eq!(limiter.limit_for(&owner("robot", None)), 5);
+        assert_eq!(limiter.limit_for(&owner("guest", Some("acme"))), 42);
+
+        limiter.set_org_limit("acme", None);
+        assert_eq!(limiter.limit_for(&owner("guest", Some("acme"))), 1);
+    }
+
+    #[test]
+    fn test_config_replaced_at_runtime() {
+        let limiter = ChainLimiter::new(ChainLimitsConfig::default(), Arc::new(ChainTracker::new()));
+        limiter.set_org_limit("acme", Some(42));
+
+        let mut config = ChainLimitsConfig::default();
+        config.role_limits.insert("guest".to_string(), 3);
+        config.enabled = false;
+        limiter.set_config(config);
+
+        assert_eq!(limiter.limit_for(&owner("guest", None)), 3);
+        assert_eq!(limiter.limit_for(&owner("guest", Some("acme"))), 42);
+        assert!(!limiter.usage(&owner("guest", None), 0).enforced);
+    }
+
+    #[test]
+    fn test_finished_chains_free_their_slot() {
+        let tracker = Arc::new(ChainTracker::new());
+        let limiter = ChainLimiter::new(ChainLimitsConfig::default(), tracker.clone());
+        let guest = owner("guest", None);
+
+        let mut first = NeuronSignal::forward("api", "n", "API", "L2", "a".into());
+        let first_id = tracker.start(&mut first);
+        assert!(limiter.admit(&guest, &first_id).is_ok());
+
+        let mut second = NeuronSignal::forward("api", "n", "API", "L2", "b".into());
+        let second_id = tracker.start(&mut second);
+        assert!(limiter.admit(&guest, &second_id).is_err());
+
+        tracker.record_step(&first, Ok("done"), 0);
+        assert!(limiter.admit(&guest, &second_id).is_ok());
+    }
+}
@@ -0,0 +1,919 @@
+//! Signal chain tracking
+//!
+//! A chain is the tree of signals spawned by a single submitted signal. The
+//! root signal's ID doubles as the chain ID and is carried to every child
+//! signal through the `chain_id` metadata key, so the router can attribute
+//! neuron outputs back to the submission that caused them.
+//!
+//! Chains that finish (complete, fail or are cancelled) are announced on a
+//! broadcast channel, see [`ChainTracker::subscribe`]. Chains are announced
+//! on a second channel once their first step is recorded, see
+//! [`ChainTracker::subscribe_started`].
+//!
+//! Backward signals are steps like any other, children of the signal whose
+//! failure they report. Their step records the gradient they carried, and
+//! the chain's result lists them as feedback, but their output is never the
+//! chain's final output.
+//!
+//! Signals parked during a provider outage stay in flight, so their chain
+//! keeps running; the result lists them as deferred branches until their
+//! step is recorded after providers recover.
+//!
+//! Outputs held for approval are not steps until they are decided on. The
+//! chain keeps running meanwhile, and its result lists them as gates.
+//!
+//! The tool sandbox records the chain's tool ledger after each tool call,
+//! so the result shows the calls made so far against their limits.
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+
+use hal9_core::{metadata_schema::keys, NeuronSignal, PropagationType};
+
+use crate::citations::{CitedMemory, NodeCitations};
+use crate::cost_tags::{self, CostTags, TAGS_KEY};
+use crate::fan_out::{self, FanOutTruncation, FanOutUsage};
+use crate::output_format::{FormatReport, OutputFormat, OUTPUT_FORMAT_KEY};
+
+pub use hal9_api_types::{ApprovalGate, ChainResult, ChainStatus, DeferredBranch, FeedbackEdge, GateStatus, ToolUsage};
+
+/// Metadata key carrying the chain ID on every signal of a chain
+pub const CHAIN_ID_KEY: &str = keys::trace::CHAIN_ID;
+
+/// Metadata key carrying the ID of the signal a child signal was produced by
+pub const PARENT_ID_KEY: &str = keys::trace::PARENT_ID;
+
+/// Metadata key carrying the ID of the chain a replayed chain was started from
+pub const REPLAY_OF_KEY: &str = keys::trace::REPLAY_OF;
+
+/// Metadata key carrying the ID of the user a chain is charged to
+pub const USER_ID_KEY: &str = keys::auth::USER_ID;
+
+/// Metadata key carrying the organization a chain is charged to
+pub const ORG_ID_KEY: &str = keys::auth::ORG_ID;
+
+/// Metadata key carrying the API key whose budget a chain is charged to
+pub const API_KEY_ID_KEY: &str = keys::auth::API_KEY_ID;
+
+/// Metadata key carrying the class of the error a backward signal reports
+pub const GRADIENT_ERROR_CLASS_KEY: &str = keys::gradient::ERROR_CLASS;
+
+/// Metadata key carrying the attenuation applied to a backward signal's gradient
+pub const GRADIENT_ATTENUATION_KEY: &str = keys::gradient::ATTENUATION;
+
+/// Record on a backward signal the class of the error it reports and the
+/// factor its gradient was scaled by
+pub fn mark_gradient(signal: &mut NeuronSignal, error_class: &str, attenuation: f32) {
+    signal.metadata.insert(GRADIENT_ERROR_CLASS_KEY.to_string(), error_class.to_string());
+    signal.metadata.insert(GRADIENT_ATTENUATION_KEY.to_string(), attenuation.to_string());
+}
+
+/// Gradient a backward signal carried
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StepGradient {
+    /// Neuron that sent the signal
+    pub from_neuron: String,
+    pub error_class: String,
+    pub magnitude: f32,
+    pub attenuation: Option<f32>,
+}
+
+impl StepGradient {
+    /// Gradient of `signal`, if it is a backward one
+    pub fn of(signal: &NeuronSignal) -> Option<Self> {
+        if signal.propagation_type != PropagationType::Backward {
+            return None;
+        }
+        let gradient = signal.payload.gradient.as_ref();
+        let error_class = signal.metadata.get(GRADIENT_ERROR_CLASS_KEY).cloned()
+            .or_else(|| gradient.map(|g| g.error_type.clone()))
+            .unwrap_or_else(|| "unknown".to_string());
+        Some(Self {
+            from_neuron: signal.from_neuron.clone(),
+            error_class,
+            magnitude: gradient.map_or(0.0, |g| g.magnitude),
+            attenuation: signal.metadata.get(GRADIENT_ATTENUATION_KEY).and_then(|a| a.parse().ok()),
+        })
+    }
+}
+
+/// A single neuron step within a chain
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChainStep {
+    pub signal_id: String,
+    /// Signal that produced this one; `None` for the chain root
+    pub parent_id: Option<String>,
+    pub neuron_id: String,
+    pub layer: String,
+    pub direction: PropagationType,
+    /// Gradient carried by a backward signal
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gradient: Option<StepGradient>,
+    /// Model that processed the signal, when known
+    pub model: Option<String>,
+    pub output: Option<String>,
+    /// How the output fared against the chain's requested format, for
+    /// terminal steps of chains that requested one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub format: Option<FormatReport>,
+    /// Memory entries the output cited
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub citations: Vec<CitedMemory>,
+    pub error: Option<String>,
+    /// Time from the signal being sent to it being processed
+    pub duration_ms: i64,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    /// Cost attribution tags the signal carried
+    #[serde(default, skip_serializing_if = "CostTags::is_empty")]
+    pub tags: CostTags,
+    pub timestamp: DateTime<Utc>,
+}
+
+impl ChainStep {
+    /// When the signal was sent
+    pub fn sent_at(&self) -> DateTime<Utc> {
+        self.timestamp - chrono::Duration::milliseconds(self.duration_ms)
+    }
+}
+
+/// A processed signal, as listed by `GET /api/v1/signals`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignalActivity {
+    pub signal_id: String,
+    pub chain_id: String,
+    pub parent_id: Option<String>,
+    /// Neuron that processed the signal
+    pub neuron_id: String,
+    pub layer: String,
+    pub direction: PropagationType,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gradient: Option<StepGradient>,
+    pub error: Option<String>,
+    pub timestamp: DateTime<Utc>,
+}
+
+impl SignalActivity {
+    fn new(chain_id: &str, step: &ChainStep) -> Self {
+        Self {
+            signal_id: step.signal_id.clone(),
+            chain_id: chain_id.to_string(),
+            parent_id: step.parent_id.clone(),
+            neuron_id: step.neuron_id.clone(),
+            layer: step.layer.clone(),
+            direction: step.direction,
+            gradient: step.gradient.clone(),
+            error: step.error.clone(),
+            timestamp: step.timestamp,
+        }
+    }
+}
+
+/// Tracked state of a chain
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChainRecord {
+    pub chain_id: String,
+    pub layer: String,
+    /// Neuron the root signal was sent to
+    pub neuron_id: String,
+    pub input: String,
+    /// Chain this one replays
+    pub replay_of: Option<String>,
+    pub user_id: Option<String>,
+    pub org_id: Option<String>,
+    /// API key the chain was started with
+    pub api_key_id: Option<String>,
+    /// Format the final output was requested in
+    pub output_format: Option<OutputFormat>,
+    /// Cost attribution tags of the root signal
+    pub tags: CostTags,
+    pub status: ChainStatus,
+    pub created_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+    pub steps: Vec<ChainStep>,
+    /// Claude tokens spent across the chain's steps
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    /// Signals the chain may process, when a node budget applies
+    #[serde(default)]
+    pub node_budget: Option<u64>,
+    /// Responses whose branches were dropped for a fan-out limit
+    #[serde(default)]
+    pub truncations: Vec<FanOutTruncation>,
+    /// Tool calls against the chain's sandbox limits, once it made one
+    #[serde(default)]
+    pub tools: Option<ToolUsage>,
+    /// Branches parked during a provider outage
+    #[serde(default)]
+    pub deferred: Vec<DeferredBranch>,
+    /// Outputs held for approval
+    #[serde(default)]
+    pub gates: Vec<ApprovalGate>,
+    /// Metadata of the root signal, to replay it with
+    #[serde(skip)]
+    pub root_metadata: HashMap<String, String>,
+    #[serde(skip)]
+    pending: usize,
+    /// Usage recorded for signals whose step is not recorded yet
+    #[serde(skip)]
+    usage: HashMap<String, StepUsage>,
+}
+
+impl ChainRecord {
+    /// The chain as it stood at `at`, for a chain created by then. One that
+    /// had not finished keeps the steps processed by `at` and counts the
+    /// signals sent by then and processed later as pending, or its root if
+    /// none were.
+    pub fn as_of(&self, at: DateTime<Utc>) -> ChainRecord {
+        let mut record = self.clone();
+        if self.completed_at.is_some_and(|done| done <= at) {
+            return record;
+        }
+        record.steps.retain(|step| step.timestamp <= at);
+        record.status = ChainStatus::Running;
+        record.completed_at = None;
+        record.prompt_tokens = record.steps.iter().map(|step| step.prompt_tokens).sum();
+        record.completion_tokens = record.steps.iter().map(|step| step.completion_tokens).sum();
+        record.truncations.retain(|truncation| {
+            record.steps.iter().any(|step| step.signal_id == truncation.signal_id)
+        });
+        if let Some(tools) = &mut record.tools {
+            tools.violations.retain(|violation| violation.at <= at);
+        }
+        record.deferred.retain(|branch| branch.deferred_at <= at);
+        for branch in &mut record.deferred {
+            branch.resumed_at = branch.resumed_at.filter(|resumed| *resumed <= at);
+        }
+        record.gates.retain(|gate| gate.requested_at <= at);
+        for gate in &mut record.gates {
+            if gate.decided_at.is_some_and(|decided| decided > at) {
+                gate.status = GateStatus::Pending;
+                gate.decided_by = None;
+                gate.edited = false;
+                gate.decided_at = None;
+            }
+        }
+        record.pending = self
+            .steps
+            .iter()
+            .filter(|step| step.timestamp > at && step.sent_at() <= at)
+            .count()
+            .max(1);
+        record
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct StepUsage {
+    prompt_tokens: u64,
+    completion_tokens: u64,
+    model: Option<String>,
+    format: Option<FormatReport>,
+    citations: Vec<CitedMemory>,
+}
+
+/// Tracks in-flight and finished chains
+pub struct ChainTracker {
+    chains: DashMap<String, ChainRecord>,
+    finished_tx: broadcast::Sender<ChainResult>,
+    started_tx: broadcast::Sender<String>,
+}
+
+impl Default for ChainTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ChainTracker {
+    /// Create a new chain tracker
+    pub fn new() -> Self {
+        let (finished_tx, _) = broadcast::channel(256);
+        let (started_tx, _) = broadcast::channel(256);
+        Self {
+            chains: DashMap::new(),
+            finished_tx,
+            started_tx,
+        }
+    }
+
+    /// Subscribe to chains as they finish
+    pub fn subscribe(&self) -> broadcast::Receiver<ChainResult> {
+        self.finished_tx.subscribe()
+    }
+
+    /// Subscribe to the IDs of chains as their first step is recorded.
+    /// Chains refused before any signal was sent are never announced.
+    pub fn subscribe_started(&self) -> broadcast::Receiver<String> {
+        self.started_tx.subscribe()
+    }
+
+    /// Get the chain ID a signal belongs to
+    pub fn chain_id_of(signal: &NeuronSignal) -> Option<&str> {
+        signal.metadata.get(CHAIN_ID_KEY).map(|s| s.as_str())
+    }
+
+    /// Start tracking a new chain rooted at `signal`.
+    ///
+    /// Tags the signal with its chain ID (the root signal ID unless already
+    /// set) and returns that ID.
+    pub fn start(&self, signal: &mut NeuronSignal) -> String {
+        let chain_id = signal
+            .metadata
+            .entry(CHAIN_ID_KEY.to_string())
+            .or_insert_with(|| signal.signal_id.to_string())
+            .clone();
+
+        self.chains.insert(
+            chain_id.clone(),
+            ChainRecord {
+                chain_id: chain_id.clone(),
+                layer: signal.layer_to.clone(),
+                neuron_id: signal.to_neuron.clone(),
+                input: signal.payload.activation.content.clone(),
+                replay_of: signal.metadata.get(REPLAY_OF_KEY).cloned(),
+                user_id: signal.metadata.get(USER_ID_KEY).cloned(),
+                org_id: signal.metadata.get(ORG_ID_KEY).cloned(),
+                api_key_id: signal.metadata.get(API_KEY_ID_KEY).cloned(),
+                output_format: signal.metadata.get(OUTPUT_FORMAT_KEY).and_then(|f| OutputFormat::parse(f)),
+                tags: tags_of(signal),
+                status: ChainStatus::Running,
+                created_at: Utc::now(),
+                completed_at: None,
+                steps: Vec::new(),
+                prompt_tokens: 0,
+                completion_tokens: 0,
+                node_budget: fan_out::budget_of(&signal.metadata),
+                truncations: Vec::new(),
+                tools: None,
+                deferred: Vec::new(),
+                gates: Vec::new(),
+                root_metadata: signal.metadata.clone(),
+                pending: 1,
+                usage: HashMap::new(),
+            },
+        );
+
+        chain_id
+    }
+
+    /// Record a processed signal and the number of child signals it spawned.
+    ///
+    /// Marks the chain finished once no signals remain in flight.
+    pub fn record_step(
+        &self,
+        signal: &NeuronSignal,
+        outcome: std::result::Result<&str, &str>,
+        children: usize,
+    ) {
+        let Some(chain_id) = Self::chain_id_of(signal) else {
+            return;
+        };
+        let Some(mut record) = self.chains.get_mut(chain_id) else {
+            return;
+        };
+
+        let (output, error) = match outcome {
+            Ok(output) => (Some(output.to_string()), None),
+            Err(error) => (None, Some(error.to_string())),
+        };
+
+        if record.steps.is_empty() {
+            let _ = self.started_tx.send(chain_id.to_string());
+        }
+
+        let signal_id = signal.signal_id.to_string();
+        let usage = record.usage.remove(&signal_id).unwrap_or_default();
+        let now = Utc::now();
+        if let Some(branch) = record.deferred.iter_mut().find(|b| b.signal_id == signal_id && b.resumed_at.is_none()) {
+            branch.resumed_at = Some(now);
+        }
+        record.steps.push(ChainStep {
+            parent_id: signal.metadata.get(PARENT_ID_KEY).cloned(),
+            signal_id,
+            neuron_id: signal.to_neuron.clone(),
+            layer: signal.layer_to.clone(),
+            direction: signal.propagation_type,
+            gradient: StepGradient::of(signal),
+            model: usage.model,
+            output,
+            format: usage.format,
+            citations: usage.citations,
+            error,
+            duration_ms: (now - signal.timestamp).num_milliseconds().max(0),
+            prompt_tokens: usage.prompt_tokens,
+            completion_tokens: usage.completion_tokens,
+            tags: tags_of(signal),
+            timestamp: now,
+        });
+
+        record.pending = record.pending.saturating_sub(1) + children;
+        self.finish_if_done(record);
+    }
+
+    /// Note that `signal` was parked until providers recover. It stays in
+    /// flight, and is listed as a deferred branch until its step is
+    /// recorded; parking it again keeps its first deferral.
+    pub fn record_deferral(&self, signal: &NeuronSignal, reason: &str) {
+        let Some(chain_id) = Self::chain_id_of(signal) else {
+            return;
+        };
+        let Some(mut record) = self.chains.get_mut(chain_id) else {
+            return;
+        };
+        let signal_id = signal.signal_id.to_string();
+        if record.deferred.iter().any(|b| b.signal_id == signal_id && b.resumed_at.is_none()) {
+            return;
+        }
+        record.deferred.push(DeferredBranch {
+            signal_id,
+            neuron_id: signal.to_neuron.clone(),
+            layer: signal.layer_to.clone(),
+            reason: reason.to_string(),
+            deferred_at: Utc::now(),
+            resumed_at: None,
+        });
+    }
+
+    /// Note that the output of `signal` is held for approval `approval_id`.
+    /// The signal stays in flight until the gate is decided.
+    pub fn record_gate(&self, signal: &NeuronSignal, approval_id: &str, reason: &str) {
+        let Some(chain_id) = Self::chain_id_of(signal) else {
+            return;
+        };
+        let Some(mut record) = self.chains.get_mut(chain_id) else {
+            return;
+        };
+        record.gates.push(ApprovalGate {
+            approval_id: approval_id.to_string(),
+            signal_id: signal.signal_id.to_string(),
+            parent_id: signal.metadata.get(PARENT_ID_KEY).cloned(),
+            neuron_id: signal.to_neuron.clone(),
+            layer: signal.layer_to.clone(),
+            reason: reason.to_string(),
+            status: GateStatus::Pending,
+            decided_by: None,
+            edited: false,
+            requested_at: Utc::now(),
+            decided_at: None,
+        });
+    }
+
+    /// Record the decision on a gate. Call before recording the step of its
+    /// signal, so a chain finishing with it includes the decision.
+    pub fn decide_gate(&self, signal: &NeuronSignal, approval_id: &str, approved: bool, actor: &str, edited: bool) {
+        let Some(chain_id) = Self::chain_id_of(signal) else {
+            return;
+        };
+        let Some(mut record) = self.chains.get_mut(chain_id) else {
+            return;
+        };
+        if let Some(gate) = record.gates.iter_mut().find(|g| g.approval_id == approval_id) {
+            gate.status = if approved { GateStatus::Approved } else { GateStatus::Rejected };
+            gate.decided_by = Some(actor.to_string());
+            gate.edited = edited;
+            gate.decided_at = Some(Utc::now());
+        }
+    }
+
+    /// Count a signal sent before its parent's step is recorded, a
+    /// speculative child the parent does not count among its children
+    pub fn speculate(&self, signal: &NeuronSignal) {
+        let Some(chain_id) = Self::chain_id_of(signal) else {
+            return;
+        };
+        if let Some(mut record) = self.chains.get_mut(chain_id) {
+            record.pending += 1;
+        }
+    }
+
+    /// Stop counting a speculative signal that was cancelled, without
+    /// recording a step for it
+    pub fn withdraw(&self, signal: &NeuronSignal) {
+        let Some(chain_id) = Self::chain_id_of(signal) else {
+            return;
+        };
+        if let Some(mut record) = self.chains.get_mut(chain_id) {
+            record.pending = record.pending.saturating_sub(1);
+            self.finish_if_done(record);
+        }
+    }
+
+    /// Mark a chain with no signals left in flight finished
+    fn finish_if_done(&self, mut record: dashmap::mapref::one::RefMut<'_, String, ChainRecord>) {
+        if record.pending == 0 && record.status == ChainStatus::Running {
+            let failed = record.steps.iter().all(|s| s.error.is_some())
+                || record.truncations.iter().any(|t| t.failed);
+            record.status = if failed {
+                ChainStatus::Failed
+            } else {
+                ChainStatus::Completed
+            };
+            record.completed_at = Some(Utc::now());
+
+            let result = summarize(&record);
+            drop(record);
+            let _ = self.finished_tx.send(result);
+        }
+    }
+
+    /// Charge tokens spent processing `signal` to its chain. Call before
+    /// [`record_step`](Self::record_step) so a finishing chain includes them.
+    pub fn record_usage(&self, signal: &NeuronSignal, prompt_tokens: u32, completion_tokens: u32) {
+        let Some(chain_id) = Self::chain_id_of(signal) else {
+            return;
+        };
+        if let Some(mut record) = self.chains.get_mut(chain_id) {
+            record.prompt_tokens += prompt_tokens as u64;
+            record.completion_tokens += completion_tokens as u64;
+            let step = record.usage.entry(signal.signal_id.to_string()).or_default();
+            step.prompt_tokens += prompt_tokens as u64;
+            step.completion_tokens += completion_tokens as u64;
+        }
+    }
+
+    /// Note the model that processed `signal`, for its step
+    pub fn record_model(&self, signal: &NeuronSignal, model: &str) {
+        let Some(chain_id) = Self::chain_id_of(signal) else {
+            return;
+        };
+        if let Some(mut record) = self.chains.get_mut(chain_id) {
+            record.usage.entry(signal.signal_id.to_string()).or_default().model = Some(model.to_string());
+        }
+    }
+
+    /// Note how the output of `signal` fared against the chain's requested
+    /// format, for its step
+    pub fn record_format(&self, signal: &NeuronSignal, report: FormatReport) {
+        let Some(chain_id) = Self::chain_id_of(signal) else {
+            return;
+        };
+        if let Some(mut record) = self.chains.get_mut(chain_id) {
+            record.usage.entry(signal.signal_id.to_string()).or_default().format = Some(report);
+        }
+    }
+
+    /// Note the memory entries the output of `signal` cited, for its step
+    pub fn record_citations(&self, signal: &NeuronSignal, cited: Vec<CitedMemory>) {
+        let Some(chain_id) = Self::chain_id_of(signal) else {
+            return;
+        };
+        if let Some(mut record) = self.chains.get_mut(chain_id) {
+            record.usage.entry(signal.signal_id.to_string()).or_default().citations = cited;
+        }
+    }
+
+    /// Note the branches dropped from the response to `signal`. Call before
+    /// [`record_step`](Self::record_step); a truncation that failed the
+    /// chain marks it failed once it finishes.
+    pub fn record_truncation(&self, signal: &NeuronSignal, truncation: FanOutTruncation) {
+        let Some(chain_id) = Self::chain_id_of(signal) else {
+            return;
+        };
+        if let Some(mut record) = self.chains.get_mut(chain_id) {
+            record.truncations.push(truncation);
+        }
+    }
+
+    /// Replace the chain's tool ledger with `usage`, its state after the
+    /// latest tool call
+    pub fn record_tools(&self, chain_id: &str, usage: ToolUsage) {
+        if let Some(mut record) = self.chains.get_mut(chain_id) {
+            record.tools = Some(usage);
+        }
+    }
+
+    /// Cancel a running chain. Signals still in flight are processed but no
+    /// longer change its status. Returns false if the chain is unknown or
+    /// already finished.
+    pub fn cancel(&self, chain_id: &str) -> bool {
+        let Some(mut record) = self.chains.get_mut(chain_id) else {
+            return false;
+        };
+        if record.status != ChainStatus::Running {
+            return false;
+        }
+
+        record.status = ChainStatus::Cancelled;
+        record.completed_at = Some(Utc::now());
+
+        let result = summarize(&record);
+        drop(record);
+        let _ = self.finished_tx.send(result);
+        true
+    }
+
+    /// Stop tracking a chain, one refused before any signal was sent or one
+    /// that was archived
+    pub fn discard(&self, chain_id: &str) {
+        self.chains.remove(chain_id);
+    }
+
+    /// Chains that finished before `cutoff`
+    pub fn finished_before(&self, cutoff: DateTime<Utc>) -> Vec<ChainRecord> {
+        self.chains
+            .iter()
+            .filter(|r| r.status != ChainStatus::Running && r.completed_at.is_some_and(|done| done < cutoff))
+            .map(|r| r.clone())
+            .collect()
+    }
+
+    /// Chains running at some point between `from` and `to`
+    pub fn overlapping(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> Vec<ChainRecord> {
+        self.chains
+            .iter()
+            .filter(|r| r.created_at <= to && r.completed_at.is_none_or(|done| done >= from))
+            .map(|r| r.clone())
+            .collect()
+    }
+
+    /// Get the raw record for a chain
+    pub fn get(&self, chain_id: &str) -> Option<ChainRecord> {
+        self.chains.get(chain_id).map(|r| r.clone())
+    }
+
+    /// Build the aggregated result for a chain
+    pub fn aggregate(&self, chain_id: &str) -> Option<ChainResult> {
+        self.chains.get(chain_id).map(|record| summarize(&record))
+    }
+
+    /// Chains replayed from `chain_id`, oldest first
+    pub fn replays_of(&self, chain_id: &str) -> Vec<String> {
+        let mut replays: Vec<_> = self.chains
+            .iter()
+            .filter(|r| r.replay_of.as_deref() == Some(chain_id))
+            .map(|r| (r.created_at, r.chain_id.clone()))
+            .collect();
+        replays.sort();
+        replays.into_iter().map(|(_, chain_id)| chain_id).collect()
+    }
+
+    /// Steps of every tracked chain processed since `since`, of signals
+    /// propagating in `direction` if given
+    pub fn activity(&self, since: Option<DateTime<Utc>>, direction: Option<PropagationType>) -> Vec<SignalActivity> {
+        self.chains
+            .iter()
+            .flat_map(|record| {
+                record.steps.iter()
+                    .filter(|step| since.is_none_or(|since| step.timestamp >= since))
+                    .filter(|step| direction.is_none_or(|direction| step.direction == direction))
+                    .map(|step| SignalActivity::new(&record.chain_id, step))
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    /// Number of chains still running
+    pub fn running_count(&self) -> usize {
+        self.chains
+            .iter()
+            .filter(|r| r.status == ChainStatus::Running)
+            .count()
+    }
+}
+
+/// Aggregate a chain record into its result
+pub fn summarize(record: &ChainRecord) -> ChainResult {
+    let mut layers: Vec<String> = Vec::new();
+    for step in &record.steps {
+        if !layers.contains(&step.layer) {
+            layers.push(step.layer.clone());
+        }
+    }
+
+    // A backward step only acknowledges the gradient it received
+    let final_step = record
+        .steps
+        .iter()
+        .filter(|s| s.output.is_some() && s.direction == PropagationType::Forward)
+        .min_by_key(|s| layer_depth(&s.layer));
+    let final_output = final_step.and_then(|s| s.output.clone());
+    let format = final_step.and_then(|s| s.format.as_ref());
+
+    let errors = record
+        .steps
+        .iter()
+        .filter_map(|s| s.error.as_ref().map(|e| format!("{}: {}", s.neuron_id, e)))
+        .collect();
+
+    let feedback: Vec<FeedbackEdge> = record
+        .steps
+        .iter()
+        .filter_map(|s| {
+            let gradient = s.gradient.as_ref()?;
+            Some(FeedbackEdge {
+                from_neuron: gradient.from_neuron.clone(),
+                to_neuron: s.neuron_id.clone(),
+                error_class: gradient.error_class.clone(),
+                magnitude: gradient.magnitude as f64,
+                attenuation: gradient.attenuation.map(f64::from),
+            })
+        })
+        .collect();
+
+    let cited_memories = record
+        .steps
+        .iter()
+        .filter(|s| !s.citations.is_empty())
+        .map(|s| NodeCitations {
+            signal_id: s.signal_id.clone(),
+            neuron_id: s.neuron_id.clone(),
+            layer: s.layer.clone(),
+            memories: s.citations.clone(),
+        })
+        .collect();
+
+    ChainResult {
+        chain_id: record.chain_id.clone(),
+        status: record.status,
+        input: record.input.clone(),
+        steps_completed: record.steps.len(),
+        pending_signals: record.pending,
+        layers,
+        final_output,
+        output_format: record.output_format,
+        format_degraded: format.is_some_and(|f| f.degraded),
+        format_reason: format.filter(|f| f.degraded).and_then(|f| f.reason.clone()),
+        errors,
+        backward_steps: record.steps.iter().filter(|s| s.direction == PropagationType::Backward).count(),
+        feedback,
+        replay_of: record.replay_of.clone(),
+        tags: record.tags.clone(),
+        fan_out: fan_out_usage(record),
+        tools: record.tools.clone(),
+        cited_memories,
+        deferred: record.deferred.clone(),
+        gates: record.gates.clone(),
+        prompt_tokens: record.prompt_tokens,
+        completion_tokens: record.completion_tokens,
+        created_at: record.created_at,
+        completed_at: record.completed_at,
+        duration_ms: record
+            .completed_at
+            .map(|done| (done - record.created_at).num_milliseconds()),
+        archived: false,
+    }
+}
+
+/// Fan-out of a chain against its limits, if one applied
+fn fan_out_usage(record: &ChainRecord) -> Option<FanOutUsage> {
+    if record.node_budget.is_none() && record.truncations.is_empty() {
+        return None;
+    }
+    Some(FanOutUsage {
+        node_budget: record.node_budget,
+        nodes: record.steps.iter().filter(|s| s.direction == PropagationType::Forward).count(),
+        truncated_branches: record.truncations.iter()
+            .map(|t| if t.failed { t.requested } else { t.requested - t.allowed })
+            .sum(),
+        truncations: record.truncations.clone(),
+    })
+}
+
+/// Cost attribution tags a signal carries; tags that don't parse were
+/// refused at submission, so they count as none
+fn tags_of(signal: &NeuronSignal) -> CostTags {
+    signal.metadata.get(TAGS_KEY)
+        .and_then(|tags| cost_tags::decode(tags).ok())
+        .unwrap_or_default()
+}
+
+/// Numeric depth of a layer name ("L3" -> 3), used to find the deepest output
+pub(crate) fn layer_depth(layer: &str) -> u8 {
+    layer
+        .trim_start_matches('L')
+        .parse()
+        .unwrap_or(u8::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chain_completes_when_all_signals_processed() {
+        let tracker = ChainTracker::new();
+        let mut root = NeuronSignal::forward("api", "n-l4", "API", "L4", "build it".into());
+        let chain_id = tracker.start(&mut root);
+        assert_eq!(chain_id, root.signal_id.to_string());
+
+        tracker.record_step(&root, Ok("FORWARD_TO: n-l3"), 1);
+        assert_eq!(tracker.get(&chain_id).unwrap().status, ChainStatus::Running);
+
+        let mut child = NeuronSignal::forward("n-l4", "n-l3", "L4", "L3", "design".into());
+        child.metadata = root.metadata.clone();
+        child.metadata.insert(PARENT_ID_KEY.to_string(), root.signal_id.to_string());
+        tracker.record_usage(&child, 120, 30);
+        tracker.record_model(&child, "claude-3-haiku");
+        tracker.record_step(&child, Ok("done"), 0);
+
+        let steps = tracker.get(&chain_id).unwrap().steps;
+        assert_eq!(steps[0].parent_id, None);
+        assert_eq!(steps[1].parent_id.as_deref(), Some(chain_id.as_str()));
+        assert_eq!((steps[1].prompt_tokens, steps[1].completion_tokens), (120, 30));
+        assert_eq!(steps[1].model.as_deref(), Some("claude-3-haiku"));
+
+        let result = tracker.aggregate(&chain_id).unwrap();
+        assert_eq!(result.status, ChainStatus::Completed);
+        assert_eq!(result.steps_completed, 2);
+        assert_eq!(result.final_output.as_deref(), Some("done"));
+        assert_eq!(tracker.running_count(), 0);
+    }
+
+    #[test]
+    fn test_backward_steps_are_feedback_not_output() {
+        let tracker = ChainTracker::new();
+        let mut root = NeuronSignal::forward("api", "n-l3", "API", "L3", "design".into());
+        let chain_id = tracker.start(&mut root);
+        tracker.record_step(&root, Ok("FORWARD_TO: n-l2"), 1);
+
+        let mut child = NeuronSignal::forward("n-l3", "n-l2", "L3", "L2", "code".into());
+        child.metadata = root.metadata.clone();
+        tracker.record_step(&child, Err("timeout"), 1);
+
+        let gradient = hal9_core::Gradient::new("timeout".to_string(), 0.5);
+        let mut feedback = NeuronSignal::backward("n-l2", "n-l3", "L2", "L3", gradient);
+        feedback.metadata = child.metadata.clone();
+        mark_gradient(&mut feedback, "timeout", 0.5);
+        tracker.record_step(&feedback, Ok("Backward propagation processed"), 0);
+
+        let result = tracker.aggregate(&chain_id).unwrap();
+        assert_eq!(result.final_output.as_deref(), Some("FORWARD_TO: n-l2"));
+        assert_eq!(result.backward_steps, 1);
+        assert_eq!(result.feedback, vec![FeedbackEdge {
+            from_neuron: "n-l2".to_string(),
+            to_neuron: "n-l3".to_string(),
+            error_class: "timeout".to_string(),
+            magnitude: 0.5,
+            attenuation: Some(0.5),
+        }]);
+
+        let backward = tracker.activity(None, Some(PropagationType::Backward));
+        assert_eq!(backward.len(), 1);
+        assert_eq!(backward[0].signal_id, feedback.signal_id.to_string());
+        assert_eq!(tracker.activity(None, None).len(), 3);
+        assert!(tracker.activity(Some(Utc::now() + chrono::Duration::minutes(1)), None).is_empty());
+    }
+
+    #[test]
+    fn test_chain_fails_when_every_step_errors() {
+        let tracker = ChainTracker::new();
+        let mut root = NeuronSignal::forward("api", "n-l2", "API", "L2", "code".into());
+        let chain_id = tracker.start(&mut root);
+
+        tracker.record_step(&root, Err("timeout"), 0);
+
+        let result = tracker.aggregate(&chain_id).unwrap();
+        assert_eq!(result.status, ChainStatus::Failed);
+        assert_eq!(result.errors.len(), 1);
+    }
+
+    #[test]
+    fn test_replays_link_to_their_source() {
+        let tracker = ChainTracker::new();
+        let mut source = NeuronSignal::forward("api", "n-l2", "API", "L2", "code".into());
+        let source_id = tracker.start(&mut source);
+
+        let mut replay = NeuronSignal::forward("api", "n-l2", "API", "L2", "better code".into());
+        replay.metadata.insert(REPLAY_OF_KEY.to_string(), source_id.clone());
+        let replay_id = tracker.start(&mut replay);
+
+        let record = tracker.get(&replay_id).unwrap();
+        assert_eq!(record.replay_of.as_deref(), Some(source_id.as_str()));
+        assert_eq!(record.neuron_id, "n-l2");
+        assert_eq!(tracker.aggregate(&replay_id).unwrap().replay_of, Some(source_id.clone()));
+        assert_eq!(tracker.replays_of(&source_id), vec![replay_id]);
+        assert!(tracker.replays_of("unknown").is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_finished_chains_are_announced() {
+        let tracker = ChainTracker::new();
+        let mut events = tracker.subscribe();
+        let mut started = tracker.subscribe_started();
+
+        let mut first = NeuronSignal::forward("api", "n-l2", "API", "L2", "a".into());
+        let first_id = tracker.start(&mut first);
+        tracker.record_step(&first, Ok("done"), 0);
+
+        let mut second = NeuronSignal::forward("api", "n-l2", "API", "L2", "b".into());
+        let second_id = tracker.start(&mut second);
+        assert!(tracker.cancel(&second_id));
+        assert!(!tracker.cancel(&second_id));
+
+        let completed = events.recv().await.unwrap();
+        assert_eq!(completed.chain_id, first_id);
+        assert_eq!(completed.status, ChainStatus::Completed);
+
+        let cancelled = events.recv().await.unwrap();
+        assert_eq!(cancelled.status, ChainStatus::Cancelled);
+
+        // The cancelled chain never had a step
+        assert_eq!(started.recv().await.unwrap(), first_id);
+        assert!(started.try_recv().is_err());
+    }
+}
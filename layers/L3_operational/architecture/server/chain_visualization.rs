@@ -0,0 +1,556 @@
+//! Chain visualization
+//!
+//! Renders the signal tree of a tracked chain as SVG, Mermaid or Graphviz
+//! DOT. Nodes are processed signals, colored by layer and annotated with
+//! duration, tokens and status; edges run from a signal to the signals its
+//! processing produced, labeled with their propagation direction. Backward
+//! edges carry gradient feedback: they are dashed, drawn in their own color
+//! and labeled with the class of the error they report. Signals whose output
+//! was held for approval are gate nodes, hexagons where the format has them,
+//! with a dashed outline while the approval is pending.
+//!
+//! SVG is laid out server-side, one row per tree depth. Chains with more
+//! signals than the node budget are cut off breadth-first, keeping the top of
+//! the tree, and say how many signals were left out.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt::Write;
+
+use serde::Deserialize;
+
+use hal9_core::PropagationType;
+
+use crate::chain_tracker::{layer_depth, ApprovalGate, ChainRecord, ChainStep, GateStatus};
+
+/// Most signals drawn before a chain is truncated
+pub const MAX_NODES: usize = 200;
+
+const NODE_WIDTH: usize = 200;
+const NODE_HEIGHT: usize = 44;
+const H_GAP: usize = 24;
+const V_GAP: usize = 48;
+const MARGIN: usize = 20;
+const TITLE_HEIGHT: usize = 24;
+const FOOTER_HEIGHT: usize = 24;
+/// Longest label line that fits in a node at the default font size
+const MAX_LABEL_CHARS: usize = 30;
+
+const EDGE_COLOR: &str = "#555555";
+const ERROR_COLOR: &str = "#c0392b";
+const GRADIENT_COLOR: &str = "#8e44ad";
+const GATE_COLOR: &str = "#d68910";
+
+/// Output format of a chain visualization
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VisualizationFormat {
+    #[default]
+    Svg,
+    Mermaid,
+    Dot,
+}
+
+impl VisualizationFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Self::Svg => "image/svg+xml",
+            Self::Mermaid => "text/vnd.mermaid; charset=utf-8",
+            Self::Dot => "text/vnd.graphviz; charset=utf-8",
+        }
+    }
+}
+
+/// A processed signal in the tree
+#[derive(Debug, Clone)]
+pub struct GraphNode {
+    pub signal_id: String,
+    pub neuron_id: String,
+    pub layer: String,
+    pub failed: bool,
+    pub duration_ms: i64,
+    pub tokens: u64,
+    /// Distance from the chain root
+    pub depth: usize,
+    /// Where the approval of the signal's output stands, if it was held
+    pub gate: Option<GateStatus>,
+}
+
+/// A parent signal producing a child, by node index
+#[derive(Debug, Clone)]
+pub struct GraphEdge {
+    pub from: usize,
+    pub to: usize,
+    pub direction: PropagationType,
+    /// Class of the error a backward edge reports
+    pub error_class: Option<String>,
+}
+
+impl GraphEdge {
+    fn label(&self) -> String {
+        match &self.error_class {
+            Some(class) => format!("{}: {}", direction_label(self.direction), class),
+            None => direction_label(self.direction).to_string(),
+        }
+    }
+
+    fn color(&self) -> &'static str {
+        match self.direction {
+            PropagationType::Forward => EDGE_COLOR,
+            PropagationType::Backward => GRADIENT_COLOR,
+        }
+    }
+}
+
+/// Signal tree of a chain, in breadth-first order
+#[derive(Debug, Clone)]
+pub struct ChainGraph {
+    pub chain_id: String,
+    pub nodes: Vec<GraphNode>,
+    pub edges: Vec<GraphEdge>,
+    /// Signals left out to stay within the node budget
+    pub truncated: usize,
+}
+
+impl ChainGraph {
+    /// Build the tree of a tracked chain, outputs still held for approval
+    /// included
+    pub fn from_record(record: &ChainRecord, max_nodes: usize) -> Self {
+        if record.gates.is_empty() {
+            return Self::from_steps(&record.chain_id, &record.steps, max_nodes);
+        }
+
+        let mut steps = record.steps.clone();
+        for gate in &record.gates {
+            if gate.status == GateStatus::Pending && !steps.iter().any(|s| s.signal_id == gate.signal_id) {
+                steps.push(held_step(gate));
+            }
+        }
+        let mut graph = Self::from_steps(&record.chain_id, &steps, max_nodes);
+        for node in &mut graph.nodes {
+            node.gate = record.gates.iter().rev().find(|g| g.signal_id == node.signal_id).map(|g| g.status);
+        }
+        graph
+    }
+
+    /// Build the tree from a chain's steps, keeping at most `max_nodes`.
+    ///
+    /// Steps whose parent was not recorded (the root, or signals logged
+    /// before parents were tracked) start trees of their own.
+    pub fn from_steps(chain_id: &str, steps: &[ChainStep], max_nodes: usize) -> Self {
+        let known: HashSet<&str> = steps.iter().map(|s| s.signal_id.as_str()).collect();
+        let mut children: HashMap<&str, Vec<usize>> = HashMap::new();
+        let mut roots = Vec::new();
+        for (index, step) in steps.iter().enumerate() {
+            match step.parent_id.as_deref().filter(|p| known.contains(p) && *p != step.signal_id) {
+                Some(parent) => children.entry(parent).or_default().push(index),
+                None => roots.push(index),
+            }
+        }
+
+        let mut graph = Self {
+            chain_id: chain_id.to_string(),
+            nodes: Vec::new(),
+            edges: Vec::new(),
+            truncated: 0,
+        };
+
+        // Seeding with every step as well covers parent cycles
+        let mut queued = vec![false; steps.len()];
+        let mut queue = VecDeque::new();
+        for seed in roots.into_iter().chain(0..steps.len()) {
+            if queued[seed] {
+                continue;
+            }
+            queued[seed] = true;
+            queue.push_back((seed, 0, None));
+
+            while let Some((index, depth, parent)) = queue.pop_front() {
+                if graph.nodes.len() == max_nodes {
+                    break;
+                }
+                let step = &steps[index];
+                let node = graph.nodes.len();
+                graph.nodes.push(GraphNode {
+                    signal_id: step.signal_id.clone(),
+                    neuron_id: step.neuron_id.clone(),
+                    layer: step.layer.clone(),
+                    failed: step.error.is_some(),
+                    duration_ms: step.duration_ms,
+                    tokens: step.prompt_tokens + step.completion_tokens,
+                    depth,
+                    gate: None,
+                });
+                if let Some(parent) = parent {
+                    graph.edges.push(GraphEdge {
+                        from: parent,
+                        to: node,
+                        direction: step.direction,
+                        error_class: step.gradient.as_ref().map(|g| g.error_class.clone()),
+                    });
+                }
+                for &child in children.get(step.signal_id.as_str()).into_iter().flatten() {
+                    if !queued[child] {
+                        queued[child] = true;
+                        queue.push_back((child, depth + 1, Some(node)));
+                    }
+                }
+            }
+        }
+
+        graph.truncated = steps.len() - graph.nodes.len();
+        graph
+    }
+
+    pub fn render(&self, format: VisualizationFormat) -> String {
+        match format {
+            VisualizationFormat::Svg => self.to_svg(),
+            VisualizationFormat::Mermaid => self.to_mermaid(),
+            VisualizationFormat::Dot => self.to_dot(),
+        }
+    }
+
+    /// Mermaid flowchart; backward edges are dotted and colored, gate
+    /// nodes are hexagons
+    pub fn to_mermaid(&self) -> String {
+        let mut out = String::from("flowchart TD\n");
+        for (index, node) in self.nodes.iter().enumerate() {
+            let (open, close) = if node.gate.is_some() { ("{{", "}}") } else { ("[", "]") };
+            let _ = writeln!(
+                out,
+                "    n{}{}\"{}<br/>{}\"{}",
+                index,
+                open,
+                mermaid_escape(&node.neuron_id),
+                mermaid_escape(&annotation(node)),
+                close
+            );
+        }
+        if self.truncated > 0 {
+            let _ = writeln!(out, "    truncated[\"{}\"]", truncation_note(self.truncated));
+        }
+
+        for edge in &self.edges {
+            let arrow = match edge.direction {
+                PropagationType::Forward => "-->",
+                PropagationType::Backward => "-.->",
+            };
+            let _ = writeln!(out, "    n{} {}|{}| n{}", edge.from, arrow, mermaid_escape(&edge.label()), edge.to);
+        }
+        for (index, edge) in self.edges.iter().enumerate() {
+            if edge.direction == PropagationType::Backward {
+                let _ = writeln!(out, "    linkStyle {} stroke:{},color:{}", index, GRADIENT_COLOR, GRADIENT_COLOR);
+            }
+        }
+
+        for (layer, nodes) in self.nodes_by_layer() {
+            let class = format!("layer_{}", class_name(layer));
+            let _ = writeln!(out, "    classDef {} fill:{},stroke:{}", class, layer_color(layer), EDGE_COLOR);
+            let ids: Vec<String> = nodes.iter().map(|index| format!("n{}", index)).collect();
+            let _ = writeln!(out, "    class {} {}", ids.join(","), class);
+        }
+        for (index, node) in self.nodes.iter().enumerate() {
+            if node.failed {
+                let _ = writeln!(out, "    style n{} stroke:{},stroke-width:2px", index, ERROR_COLOR);
+            } else if let Some(gate) = node.gate {
+                let dash = if gate == GateStatus::Pending { ",stroke-dasharray:4 4" } else { "" };
+                let _ = writeln!(out, "    style n{} stroke:{},stroke-width:2px{}", index, GATE_COLOR, dash);
+            }
+        }
+        if self.truncated > 0 {
+            let _ = writeln!(out, "    style truncated stroke-dasharray:4 4");
+        }
+        out
+    }
+
+    /// Graphviz digraph; backward edges are dashed and colored
+    pub fn to_dot(&self) -> String {
+        let mut out = String::from("digraph chain {\n");
+        let _ = writeln!(out, "    label=\"Chain {}\";", dot_escape(&self.chain_id));
+        out.push_str("    labelloc=t;\n");
+        out.push_str("    rankdir=TB;\n");
+        out.push_str("    node [shape=box, style=\"rounded,filled\", fontname=\"Helvetica\", fontsize=10];\n");
+        out.push_str("    edge [fontname=\"Helvetica\", fontsize=9];\n");
+
+        for (index, node) in self.nodes.iter().enumerate() {
+            let mut border = match (node.failed, node.gate) {
+                (true, _) => format!(", color=\"{}\", penwidth=2", ERROR_COLOR),
+                (false, Some(_)) => format!(", color=\"{}\", penwidth=2", GATE_COLOR),
+                (false, None) => String::new(),
+            };
+            match node.gate {
+                Some(GateStatus::Pending) => border.push_str(", shape=hexagon, style=\"filled,dashed\""),
+                Some(_) => border.push_str(", shape=hexagon"),
+                None => {}
+            }
+            let _ = writeln!(
+                out,
+                "    n{} [label=\"{}\\n{}\", fillcolor=\"{}\"{}];",
+                index,
+                dot_escape(&node.neuron_id),
+                dot_escape(&annotation(node)),
+                layer_color(&node.layer),
+                border
+            );
+        }
+        if self.truncated > 0 {
+            let _ = writeln!(
+                out,
+                "    truncated [label=\"{}\", shape=note, style=dashed];",
+                truncation_note(self.truncated)
+            );
+        }
+
+        for edge in &self.edges {
+            let style = match edge.direction {
+                PropagationType::Forward => String::new(),
+                PropagationType::Backward => {
+                    format!(", style=dashed, color=\"{c}\", fontcolor=\"{c}\"", c = GRADIENT_COLOR)
+                }
+            };
+            let _ = writeln!(
+                out,
+                "    n{} -> n{} [label=\"{}\"{}];",
+                edge.from,
+                edge.to,
+                dot_escape(&edge.label()),
+                style
+            );
+        }
+        out.push_str("}\n");
+        out
+    }
+
+    /// Standalone SVG with one row of nodes per tree depth
+    pub fn to_svg(&self) -> String {
+        let rows = self.nodes.iter().map(|n| n.depth + 1).max().unwrap_or(0);
+        let mut row_sizes = vec![0usize; rows];
+        for node in &self.nodes {
+            row_sizes[node.depth] += 1;
+        }
+        let columns = row_sizes.iter().copied().max().unwrap_or(0).max(1);
+
+        // Rows are centered under the widest one
+        let mut next_column = vec![0usize; rows];
+        let positions: Vec<(usize, usize)> = self
+            .nodes
+            .iter()
+            .map(|node| {
+                let offset = (columns - row_sizes[node.depth]) * (NODE_WIDTH + H_GAP) / 2;
+                let x = MARGIN + offset + next_column[node.depth] * (NODE_WIDTH + H_GAP);
+                let y = MARGIN + TITLE_HEIGHT + node.depth * (NODE_HEIGHT + V_GAP);
+                next_column[node.depth] += 1;
+                (x, y)
+            })
+            .collect();
+
+        let width = 2 * MARGIN + columns * (NODE_WIDTH + H_GAP) - H_GAP;
+        let mut height = 2 * MARGIN + TITLE_HEIGHT + (rows * (NODE_HEIGHT + V_GAP)).saturating_sub(V_GAP);
+        if self.truncated > 0 {
+            height += FOOTER_HEIGHT;
+        }
+
+        let mut out = String::new();
+        let _ = writeln!(
+            out,
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\" \
+             font-family=\"Helvetica, Arial, sans-serif\" font-size=\"11\">",
+            w = width,
+            h = height
+        );
+        out.push_str("  <defs>");
+        for (id, color) in [("arrow", EDGE_COLOR), ("arrow-backward", GRADIENT_COLOR)] {
+            let _ = write!(
+                out,
+                "<marker id=\"{}\" viewBox=\"0 0 10 10\" refX=\"10\" refY=\"5\" markerWidth=\"6\" \
+                 markerHeight=\"6\" orient=\"auto\"><path d=\"M 0 0 L 10 5 L 0 10 z\" fill=\"{}\"/></marker>",
+                id, color
+            );
+        }
+        out.push_str("</defs>\n");
+        let _ = writeln!(
+            out,
+            "  <text x=\"{}\" y=\"{}\" font-size=\"13\" font-weight=\"bold\">Chain {}</text>",
+            MARGIN,
+            MARGIN + 12,
+            xml_escape(&self.chain_id)
+        );
+
+        for edge in &self.edges {
+            let (from_x, from_y) = positions[edge.from];
+            let (to_x, to_y) = positions[edge.to];
+            let (x1, y1) = (from_x + NODE_WIDTH / 2, from_y + NODE_HEIGHT);
+            let (x2, y2) = (to_x + NODE_WIDTH / 2, to_y);
+            let (dash, marker) = match edge.direction {
+                PropagationType::Forward => ("", "arrow"),
+                PropagationType::Backward => (" stroke-dasharray=\"4 3\"", "arrow-backward"),
+            };
+            let _ = writeln!(
+                out,
+                "  <line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" stroke=\"{}\"{} marker-end=\"url(#{})\"/>",
+                x1, y1, x2, y2, edge.color(), dash, marker
+            );
+            let _ = writeln!(
+                out,
+                "  <text x=\"{}\" y=\"{}\" fill=\"{}\" font-size=\"9\">{}</text>",
+                (x1 + x2) / 2 + 4,
+                (y1 + y2) / 2,
+                edge.color(),
+                xml_escape(&edge.label())
+            );
+        }
+
+        for (node, &(x, y)) in self.nodes.iter().zip(&positions) {
+            let (stroke, stroke_width) = match (node.failed, node.gate) {
+                (true, _) => (ERROR_COLOR, 2),
+                (false, Some(_)) => (GATE_COLOR, 2),
+                (false, None) => (EDGE_COLOR, 1),
+            };
+            let dash = if node.gate == Some(GateStatus::Pending) { " stroke-dasharray=\"4 3\"" } else { "" };
+            let center = x + NODE_WIDTH / 2;
+            let _ = writeln!(out, "  <g>");
+            let _ = writeln!(out, "    <title>{}</title>", xml_escape(&node.signal_id));
+            let _ = writeln!(
+                out,
+                "    <rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" rx=\"6\" fill=\"{}\" stroke=\"{}\" stroke-width=\"{}\"{}/>",
+                x,
+                y,
+                NODE_WIDTH,
+                NODE_HEIGHT,
+                layer_color(&node.layer),
+                stroke,
+                stroke_width,
+                dash
+            );
+            let _ = writeln!(
+                out,
+                "    <text x=\"{}\" y=\"{}\" text-anchor=\"middle\" font-weight=\"bold\">{}</text>",
+                center,
+                y + 18,
+                xml_escape(&clip(&node.neuron_id))
+            );
+            let _ = writeln!(
+                out,
+                "    <text x=\"{}\" y=\"{}\" text-anchor=\"middle\">{}</text>",
+                center,
+                y + 34,
+                xml_escape(&clip(&annotation(node)))
+            );
+            let _ = writeln!(out, "  </g>");
+        }
+
+        if self.truncated > 0 {
+            let _ = writeln!(
+                out,
+                "  <text x=\"{}\" y=\"{}\" fill=\"{}\">{}</text>",
+                MARGIN,
+                height - MARGIN,
+                ERROR_COLOR,
+                truncation_note(self.truncated)
+            );
+        }
+        out.push_str("</svg>\n");
+        out
+    }
+
+    /// Node indexes grouped by layer, in order of first appearance
+    fn nodes_by_layer(&self) -> Vec<(&str, Vec<usize>)> {
+        let mut groups: Vec<(&str, Vec<usize>)> = Vec::new();
+        for (index, node) in self.nodes.iter().enumerate() {
+            match groups.iter_mut().find(|(layer, _)| *layer == node.layer) {
+                Some((_, nodes)) => nodes.push(index),
+                None => groups.push((&node.layer, vec![index])),
+            }
+        }
+        groups
+    }
+}
+
+/// Second label line: layer, duration, tokens and status, or where the
+/// approval of a gate node stands
+fn annotation(node: &GraphNode) -> String {
+    let duration = if node.duration_ms < 1000 {
+        format!("{}ms", node.duration_ms)
+    } else {
+        format!("{:.1}s", node.duration_ms as f64 / 1000.0)
+    };
+    let status = match (node.gate, node.failed) {
+        (Some(GateStatus::Pending), _) => "pending",
+        (Some(GateStatus::Approved), _) => "approved",
+        (Some(GateStatus::Rejected), _) => "rejected",
+        (None, true) => "error",
+        (None, false) => "ok",
+    };
+    format!("{}, {}, {} tok, {}", node.layer, duration, node.tokens, status)
+}
+
+/// Stand-in step for a signal whose output is still held for approval
+fn held_step(gate: &ApprovalGate) -> ChainStep {
+    ChainStep {
+        signal_id: gate.signal_id.clone(),
+        parent_id: gate.parent_id.clone(),
+        neuron_id: gate.neuron_id.clone(),
+        layer: gate.layer.clone(),
+        direction: PropagationType::Forward,
+        gradient: None,
+        model: None,
+        output: None,
+        format: None,
+        citations: Vec::new(),
+        error: None,
+        duration_ms: 0,
+        prompt_tokens: 0,
+        completion_tokens: 0,
+        tags: Default::default(),
+        timestamp: gate.requested_at,
+    }
+}
+
+fn direction_label(direction: PropagationType) -> &'static str {
+    match direction {
+        PropagationType::Forward => "forward",
+        PropagationType::Backward => "backward",
+    }
+}
+
+fn truncation_note(truncated: usize) -> String {
+    format!("{} more signals not shown", truncated)
+}
+
+/// Fill color of a layer, from warm (L1) to cool (L9)
+fn layer_color(layer: &str) -> &'static str {
+    const PALETTE: [&str; 9] = [
+        "#fde2e2", "#fdebd3", "#fdf6c8", "#e2f5d4", "#d4f1ee", "#d8e8fb", "#e1dcfa", "#f0dcf7", "#e8e8e8",
+    ];
+    match layer_depth(layer) {
+        depth @ 1..=9 => PALETTE[depth as usize - 1],
+        _ => "#f5f5f5",
+    }
+}
+
+fn class_name(layer: &str) -> String {
+    layer.chars().map(|c| if c.is_ascii_alphanumeric() { c } else { '_' }).collect()
+}
+
+fn clip(text: &str) -> String {
+    if text.chars().count() <= MAX_LABEL_CHARS {
+        return text.to_string();
+    }
+    let mut clipped: String = text.chars().take(MAX_LABEL_CHARS - 1).collect();
+    clipped.push('…');
+    clipped
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn mermaid_escape(text: &str) -> String {
+    text.replace('"', "#quot;").replace('<', "#lt;").replace('>', "#gt;")
+}
+
+fn dot_escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
@@ -0,0 +1,169 @@
+//! Assertions for chaos tests
+//!
+//! [`run_chains`] submits a batch of chains and waits for them; running one
+//! batch before injecting a fault and one while it is active gives a
+//! baseline and a run to compare with [`assert_resilient`]. A chain counts
+//! as completed when it finished without errors, including steps error
+//! recovery brought back.
+
+use std::time::Duration;
+
+use hal9_core::NeuronSignal;
+
+use crate::chain_tracker::{ChainResult, ChainStatus};
+use crate::error::ServerResult;
+use crate::server::HAL9Server;
+
+/// How often a running chain is checked on
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Pass thresholds of a chaos scenario
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Thresholds {
+    /// Share of chains that must complete within the latency budget
+    pub min_completion: f64,
+    /// Latency budget as a multiple of the baseline's p95 chain duration
+    pub max_slowdown: f64,
+}
+
+/// What the nightly chaos suite holds the server to: 95% of chains complete
+/// within twice the baseline's p95 latency
+pub const NIGHTLY: Thresholds = Thresholds {
+    min_completion: 0.95,
+    max_slowdown: 2.0,
+};
+
+/// Chains of one batch, finished or given up on
+#[derive(Debug, Clone)]
+pub struct ChaosRun {
+    pub results: Vec<ChainResult>,
+}
+
+impl ChaosRun {
+    pub fn len(&self) -> usize {
+        self.results.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.results.is_empty()
+    }
+
+    /// Chains that finished without errors
+    pub fn completed(&self) -> impl Iterator<Item = &ChainResult> {
+        self.results.iter().filter(|r| is_completed(r))
+    }
+
+    pub fn completion_rate(&self) -> f64 {
+        share(self.completed().count(), self.len())
+    }
+
+    /// Share of chains that completed in at most `limit_ms`
+    pub fn completed_within(&self, limit_ms: i64) -> f64 {
+        let within = self.completed().filter(|r| r.duration_ms.is_some_and(|ms| ms <= limit_ms)).count();
+        share(within, self.len())
+    }
+
+    /// Duration of completed chains at percentile `p` (0 to 1), nearest rank
+    pub fn percentile_ms(&self, p: f64) -> Option<i64> {
+        let mut durations: Vec<i64> = self.completed().filter_map(|r| r.duration_ms).collect();
+        if durations.is_empty() {
+            return None;
+        }
+        durations.sort_unstable();
+        let rank = ((p.clamp(0.0, 1.0) * durations.len() as f64).ceil() as usize).max(1);
+        Some(durations[rank - 1])
+    }
+
+    /// Errors of the chains that did not complete, for failure messages
+    pub fn errors(&self) -> Vec<&str> {
+        self.results
+            .iter()
+            .filter(|r| !is_completed(r))
+            .flat_map(|r| r.errors.iter().map(String::as_str))
+            .collect()
+    }
+}
+
+fn is_completed(result: &ChainResult) -> bool {
+    result.status == ChainStatus::Completed && result.errors.is_empty()
+}
+
+fn share(count: usize, total: usize) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        count as f64 / total as f64
+    }
+}
+
+/// Submit `signals` as chains, at most `concurrency` at a time, and wait for
+/// each to finish. Chains still running after `timeout` are kept as they are
+/// and count as not completed.
+pub async fn run_chains(
+    server: &HAL9Server,
+    signals: impl IntoIterator<Item = NeuronSignal>,
+    concurrency: usize,
+    timeout: Duration,
+) -> ServerResult<ChaosRun> {
+    let signals: Vec<NeuronSignal> = signals.into_iter().collect();
+    let mut results = Vec::with_capacity(signals.len());
+    for batch in signals.chunks(concurrency.max(1)) {
+        let mut chain_ids = Vec::with_capacity(batch.len());
+        for signal in batch {
+            chain_ids.push(server.submit_signal(signal.clone()).await?);
+        }
+        let finished = futures::future::join_all(chain_ids.iter().map(|id| wait_for_chain(server, id, timeout))).await;
+        for result in finished {
+            results.push(result?);
+        }
+    }
+    Ok(ChaosRun { results })
+}
+
+async fn wait_for_chain(server: &HAL9Server, chain_id: &str, timeout: Duration) -> ServerResult<ChainResult> {
+    let deadline = tokio::time::Instant::now() + timeout;
+    loop {
+        let result = server.get_chain_result(chain_id).await?;
+        if result.status != ChainStatus::Running || tokio::time::Instant::now() >= deadline {
+            return Ok(result);
+        }
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+}
+
+/// Panic unless at least `min` of `run`'s chains completed
+pub fn assert_completion_rate(run: &ChaosRun, min: f64) {
+    let rate = run.completion_rate();
+    assert!(
+        rate >= min,
+        "{:.1}% of {} chains completed, expected at least {:.1}%; errors: {:?}",
+        rate * 100.0,
+        run.len(),
+        min * 100.0,
+        run.errors()
+    );
+}
+
+/// Panic unless `run` meets `thresholds` against `baseline`, a batch of the
+/// same chains without faults
+pub fn assert_resilient(baseline: &ChaosRun, run: &ChaosRun, thresholds: Thresholds) {
+    assert_completion_rate(baseline, 1.0);
+    let baseline_p95 = baseline.percentile_ms(0.95).unwrap_or_default();
+    // Chains complete within the same millisecond on an idle machine
+    let budget_ms = ((baseline_p95.max(1) as f64) * thresholds.max_slowdown).ceil() as i64;
+    let within = run.completed_within(budget_ms);
+    assert!(
+        within >= thresholds.min_completion,
+        "{:.1}% of {} chains completed within {}ms ({}x the baseline p95 of {}ms), expected at least {:.1}%; \
+         {:.1}% completed at all, p95 {:?}ms; errors: {:?}",
+        within * 100.0,
+        run.len(),
+        budget_ms,
+        thresholds.max_slowdown,
+        baseline_p95,
+        thresholds.min_completion * 100.0,
+        run.completion_rate() * 100.0,
+        run.percentile_ms(0.95),
+        run.errors()
+    );
+}
@@ -0,0 +1,381 @@
+//! Active injections and the hooks that apply them
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use parking_lot::Mutex;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+use tracing::{debug, info};
+use uuid::Uuid;
+
+use hal9_core::{Error, Result};
+
+use crate::claude::{ClaudeInterface, TokenUsage};
+use crate::error::{ServerError, ServerResult};
+use crate::simulation::SimRng;
+
+/// Whether this build accepts injections
+pub const AVAILABLE: bool = cfg!(any(debug_assertions, feature = "chaos"));
+
+/// Longest an injection may run
+pub const MAX_DURATION: Duration = Duration::from_secs(3600);
+
+/// Actor recorded when an injection runs out
+const EXPIRY_ACTOR: &str = "expiry";
+
+/// A kind of failure to inject
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Fault {
+    /// Fail signals as a neuron dying while processing them would
+    KillNeuron,
+    /// Hold signals for `delay_ms` before a neuron processes them
+    DelayLayer { delay_ms: u64 },
+    /// Silently drop signals sent to peer servers
+    DropMessages,
+    /// Garble what plugin neurons return
+    CorruptPlugin,
+    /// Provider calls wait for a connection that never frees up, until the
+    /// neuron's timeout or the injection's end
+    ExhaustClaudePool,
+}
+
+impl Fault {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Fault::KillNeuron => "kill_neuron",
+            Fault::DelayLayer { .. } => "delay_layer",
+            Fault::DropMessages => "drop_messages",
+            Fault::CorruptPlugin => "corrupt_plugin",
+            Fault::ExhaustClaudePool => "exhaust_claude_pool",
+        }
+    }
+}
+
+/// Body of `POST /api/v1/admin/chaos`
+#[derive(Debug, Clone, Deserialize)]
+pub struct ChaosRequest {
+    #[serde(flatten)]
+    pub fault: Fault,
+    /// Neuron ID or layer (e.g. "L3") the fault applies to; the peer server
+    /// ID for dropped messages and the plugin ID for corrupted responses.
+    /// Everything when absent.
+    #[serde(default)]
+    pub target: Option<String>,
+    /// Share of matching signals or calls affected
+    #[serde(default = "full_rate")]
+    pub rate: f64,
+    pub duration_secs: u64,
+}
+
+fn full_rate() -> f64 {
+    1.0
+}
+
+/// An injected fault, as reported
+#[derive(Debug, Clone, Serialize)]
+pub struct ChaosInjection {
+    pub id: Uuid,
+    #[serde(flatten)]
+    pub fault: Fault,
+    pub target: Option<String>,
+    pub rate: f64,
+    /// Who injected the fault, when known
+    pub created_by: Option<String>,
+    pub started_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    /// Signals or calls affected so far
+    pub hits: u64,
+}
+
+impl ChaosInjection {
+    /// Whether the fault applies to `neuron_id` on `layer`
+    fn targets_neuron(&self, neuron_id: &str, layer: &str) -> bool {
+        match &self.target {
+            Some(target) => target == neuron_id || target.eq_ignore_ascii_case(layer),
+            None => true,
+        }
+    }
+
+    /// Whether the fault applies to the peer server or plugin `id`
+    fn targets(&self, id: &str) -> bool {
+        self.target.as_deref().is_none_or(|target| target == id)
+    }
+}
+
+/// Injections as reported by `GET /api/v1/admin/chaos`
+#[derive(Debug, Clone, Serialize)]
+pub struct ChaosStatus {
+    /// Whether this build accepts injections
+    pub available: bool,
+    pub injections: Vec<ChaosInjection>,
+}
+
+/// Holds active injections and decides which signals and calls they hit
+pub struct ChaosEngine {
+    injections: Mutex<Vec<ChaosInjection>>,
+    /// Set while any injection is held, so hooks skip the lock otherwise
+    active: AtomicBool,
+    rng: SimRng,
+}
+
+impl ChaosEngine {
+    /// Engine drawing which calls an injection hits from `rng`, seeded in
+    /// simulation mode
+    pub fn new(rng: SimRng) -> Self {
+        Self {
+            injections: Mutex::new(Vec::new()),
+            active: AtomicBool::new(false),
+            rng,
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.active.load(Ordering::Relaxed)
+    }
+
+    /// Start injecting a fault
+    pub fn inject(&self, request: ChaosRequest, actor: Option<&str>) -> ServerResult<ChaosInjection> {
+        if !AVAILABLE {
+            return Err(ServerError::Forbidden(
+                "Chaos injection is not available in this build; rebuild with --features chaos".to_string(),
+            ));
+        }
+        if !(request.rate > 0.0 && request.rate <= 1.0) {
+            return Err(ServerError::InvalidInput("rate must be greater than 0 and at most 1".to_string()));
+        }
+        let duration = Duration::from_secs(request.duration_secs);
+        if duration.is_zero() || duration > MAX_DURATION {
+            return Err(ServerError::InvalidInput(format!(
+                "duration_secs must be between 1 and {}",
+                MAX_DURATION.as_secs()
+            )));
+        }
+        if let Fault::DelayLayer { delay_ms: 0 } = request.fault {
+            return Err(ServerError::InvalidInput("delay_ms must be positive".to_string()));
+        }
+
+        let started_at = Utc::now();
+        let injection = ChaosInjection {
+            id: Uuid::new_v4(),
+            fault: request.fault,
+            target: request.target,
+            rate: request.rate,
+            created_by: actor.map(str::to_string),
+            started_at,
+            expires_at: started_at + chrono::Duration::seconds(request.duration_secs as i64),
+            hits: 0,
+        };
+
+        info!(
+            target: "audit",
+            event = "chaos_injected",
+            injection = %injection.id,
+            fault = injection.fault.name(),
+            target_id = injection.target.as_deref().unwrap_or("*"),
+            rate = injection.rate,
+            actor = actor.unwrap_or("unknown"),
+            expires_at = %injection.expires_at,
+            "Injected {} for {}s", injection.fault.name(), request.duration_secs
+        );
+        let mut injections = self.injections.lock();
+        injections.push(injection.clone());
+        self.active.store(true, Ordering::Relaxed);
+        Ok(injection)
+    }
+
+    /// Stop an injection before it runs out
+    pub fn stop(&self, id: Uuid, actor: Option<&str>) -> ServerResult<ChaosInjection> {
+        let mut injections = self.injections.lock();
+        let index = injections
+            .iter()
+            .position(|i| i.id == id)
+            .ok_or_else(|| ServerError::NotFound(format!("Chaos injection {} not found", id)))?;
+        let injection = injections.remove(index);
+        self.active.store(!injections.is_empty(), Ordering::Relaxed);
+        drop(injections);
+
+        audit_stopped(&injection, "chaos_stopped", actor.unwrap_or("unknown"));
+        Ok(injection)
+    }
+
+    /// Stop every injection, returning how many were active
+    pub fn clear(&self, actor: Option<&str>) -> usize {
+        let stopped = std::mem::take(&mut *self.injections.lock());
+        self.active.store(false, Ordering::Relaxed);
+        for injection in &stopped {
+            audit_stopped(injection, "chaos_stopped", actor.unwrap_or("unknown"));
+        }
+        stopped.len()
+    }
+
+    /// Active injections, oldest first
+    pub fn status(&self) -> ChaosStatus {
+        let mut injections = self.injections.lock();
+        self.expire(&mut injections, Utc::now());
+        ChaosStatus {
+            available: AVAILABLE,
+            injections: injections.clone(),
+        }
+    }
+
+    /// Drop injections past their end. Expiry is noticed by the next hook or
+    /// status call rather than by a timer.
+    fn expire(&self, injections: &mut Vec<ChaosInjection>, now: DateTime<Utc>) {
+        injections.retain(|injection| {
+            let live = injection.expires_at > now;
+            if !live {
+                audit_stopped(injection, "chaos_expired", EXPIRY_ACTOR);
+            }
+            live
+        });
+        self.active.store(!injections.is_empty(), Ordering::Relaxed);
+    }
+
+    /// The first active injection `applies` to that also hits this call,
+    /// drawn at its rate
+    fn strike(&self, applies: impl Fn(&ChaosInjection) -> bool) -> Option<ChaosInjection> {
+        if !self.is_active() {
+            return None;
+        }
+        let mut injections = self.injections.lock();
+        self.expire(&mut injections, Utc::now());
+        let mut rng = self.rng.clone();
+        let injection = injections
+            .iter_mut()
+            .filter(|injection| applies(injection))
+            .find(|injection| injection.rate >= 1.0 || rng.gen_bool(injection.rate))?;
+        injection.hits += 1;
+        Some(injection.clone())
+    }
+
+    /// Apply delays and kills to a signal `neuron_id` is about to process
+    pub async fn before_processing(&self, neuron_id: &str, layer: &str) -> Result<()> {
+        if !self.is_active() {
+            return Ok(());
+        }
+
+        let delay = self.strike(|i| matches!(i.fault, Fault::DelayLayer { .. }) && i.targets_neuron(neuron_id, layer));
+        if let Some(ChaosInjection { fault: Fault::DelayLayer { delay_ms }, .. }) = delay {
+            debug!("Chaos delaying {} on {} by {}ms", neuron_id, layer, delay_ms);
+            tokio::time::sleep(Duration::from_millis(delay_ms)).await;
+        }
+
+        if let Some(kill) = self.strike(|i| i.fault == Fault::KillNeuron && i.targets_neuron(neuron_id, layer)) {
+            return Err(Error::Process(format!(
+                "Neuron {} was killed mid-signal by chaos injection {}",
+                neuron_id, kill.id
+            )));
+        }
+        Ok(())
+    }
+
+    /// Whether to drop a signal on its way to the peer server `peer`
+    pub fn drops_message(&self, peer: &str) -> bool {
+        self.strike(|i| i.fault == Fault::DropMessages && i.targets(peer)).is_some()
+    }
+
+    /// Garbled version of what the plugin `plugin` returned, when an
+    /// injection hits it: the first half of the content followed by
+    /// replacement characters, as a response cut off mid-stream
+    pub fn corrupt_plugin_response(&self, plugin: &str, content: &str) -> Option<String> {
+        self.strike(|i| i.fault == Fault::CorruptPlugin && i.targets(plugin))?;
+        let kept = content.chars().count() / 2;
+        let mut corrupted: String = content.chars().take(kept).collect();
+        corrupted.push_str("\u{fffd}\u{fffd}\u{fffd}");
+        Some(corrupted)
+    }
+
+    /// Put the engine in front of a neuron's Claude instance, so pool
+    /// exhaustion can reach its calls
+    pub fn wrap_claude(
+        self: &Arc<Self>,
+        claude: Box<dyn ClaudeInterface>,
+        neuron_id: &str,
+        layer: &str,
+    ) -> Box<dyn ClaudeInterface> {
+        if !AVAILABLE {
+            return claude;
+        }
+        Box::new(ChaosClaude {
+            inner: claude,
+            neuron_id: neuron_id.to_string(),
+            layer: layer.to_string(),
+            engine: self.clone(),
+        })
+    }
+}
+
+impl Default for ChaosEngine {
+    fn default() -> Self {
+        Self::new(SimRng::from_entropy())
+    }
+}
+
+fn audit_stopped(injection: &ChaosInjection, event: &str, actor: &str) {
+    info!(
+        target: "audit",
+        event,
+        injection = %injection.id,
+        fault = injection.fault.name(),
+        target_id = injection.target.as_deref().unwrap_or("*"),
+        actor,
+        hits = injection.hits,
+        "Stopped {} injection {}", injection.fault.name(), injection.id
+    );
+}
+
+/// Claude instance whose calls hang while the connection pool is exhausted
+pub struct ChaosClaude {
+    inner: Box<dyn ClaudeInterface>,
+    neuron_id: String,
+    layer: String,
+    engine: Arc<ChaosEngine>,
+}
+
+impl ChaosClaude {
+    /// Fail the call once the pool exhaustion targeting this neuron ends
+    async fn check_pool(&self) -> Result<()> {
+        let exhausted = self
+            .engine
+            .strike(|i| i.fault == Fault::ExhaustClaudePool && i.targets_neuron(&self.neuron_id, &self.layer));
+        if let Some(injection) = exhausted {
+            // No connection frees up before the injection ends; the neuron's
+            // own timeout normally fires first
+            let wait = (injection.expires_at - Utc::now()).to_std().unwrap_or_default();
+            debug!("Chaos holding Claude call of {} for up to {:?}", self.neuron_id, wait);
+            tokio::time::sleep(wait).await;
+            return Err(Error::ClaudeApi(format!(
+                "No connection available: pool exhausted by chaos injection {}",
+                injection.id
+            )));
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl ClaudeInterface for ChaosClaude {
+    async fn send_message(&self, message: &str) -> Result<String> {
+        self.check_pool().await?;
+        self.inner.send_message(message).await
+    }
+
+    async fn stream_message(&self, message: &str, partial: &mpsc::UnboundedSender<String>) -> Result<String> {
+        self.check_pool().await?;
+        self.inner.stream_message(message, partial).await
+    }
+
+    fn system_prompt(&self) -> &str {
+        self.inner.system_prompt()
+    }
+
+    fn last_token_usage(&self) -> Option<TokenUsage> {
+        self.inner.last_token_usage()
+    }
+}
@@ -0,0 +1,18 @@
+//! Chaos injection for resilience testing
+//!
+//! Faults are injected at runtime through `/api/v1/admin/chaos`, each for a
+//! limited time and against a scope: neurons killed mid-signal, a layer's
+//! traffic delayed, messages to peer servers dropped, plugin responses
+//! corrupted, or the Claude connection pool exhausted. Injections, stops and
+//! expiries are written to the audit log. [`assertions`] runs batches of
+//! chains under a fault and checks them against a baseline.
+//!
+//! Only debug builds and builds with the `chaos` feature accept injections.
+//! With nothing injected each hook costs one atomic load.
+
+pub mod assertions;
+pub mod engine;
+
+pub use engine::{
+    ChaosClaude, ChaosEngine, ChaosInjection, ChaosRequest, ChaosStatus, Fault, AVAILABLE, MAX_DURATION,
+};
@@ -1,8 +1,9 @@
 //! Circuit breaker pattern for fault tolerance
 
 use std::sync::Arc;
-use std::time::{Duration, Instant};
+use std::time::Duration;
 use tokio::sync::RwLock;
+use tokio::time::Instant;
 use tracing::{info, warn};
 
 /// Circuit breaker states
@@ -16,6 +17,16 @@ pub enum CircuitState {
     HalfOpen,
 }
 
+impl CircuitState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CircuitState::Closed => "closed",
+            CircuitState::Open => "open",
+            CircuitState::HalfOpen => "half_open",
+        }
+    }
+}
+
 /// Circuit breaker configuration
 #[derive(Debug, Clone)]
 pub struct CircuitBreakerConfig {
@@ -0,0 +1,159 @@
+//! Citations of injected memory in neuron outputs
+//!
+//! Each memory entry a neuron's prompt recalls is tagged with a reference
+//! id derived from its entry ID (`[mem-1a2b3c4d]`), and the layer system
+//! prompts ask the model to end its response with a trailer naming the ids
+//! it relied on:
+//!
+//! ```text
+//! CITATIONS: mem-1a2b3c4d, mem-5e6f7a8b
+//! ```
+//!
+//! The trailer is split off the response before it is validated or
+//! forwarded. Its ids are resolved against the entries the prompt actually
+//! recalled, so an id the model made up cites nothing. Cited entries have
+//! their citation count raised, are listed on the signals the response
+//! produced under `memory.cited` and on the chain result per step.
+
+use std::collections::HashMap;
+
+use uuid::Uuid;
+
+use hal9_core::memory::MemoryEntry;
+use hal9_core::metadata_schema::keys;
+
+pub use hal9_api_types::{CitedMemory, NodeCitations};
+
+/// Signal metadata key listing the memory entries the response that
+/// produced the signal cited
+pub const CITED_KEY: &str = keys::memory::CITED;
+
+/// Prefix of a memory reference id
+pub const REFERENCE_PREFIX: &str = "mem-";
+
+/// Hex digits of the entry ID a reference id keeps
+const REFERENCE_DIGITS: usize = 8;
+
+/// Labels a trailer line may start with, compared case-insensitively
+const LABELS: [&str; 3] = ["citations", "citation", "cited"];
+
+/// Reference id an entry is given in prompts
+pub fn reference_of(id: &Uuid) -> String {
+    format!("{}{}", REFERENCE_PREFIX, &id.simple().to_string()[..REFERENCE_DIGITS])
+}
+
+/// An entry recalled into a prompt, as it would be cited
+pub fn injected(entry: &MemoryEntry) -> CitedMemory {
+    CitedMemory {
+        reference: reference_of(&entry.id),
+        memory_id: entry.id.to_string(),
+        namespace: entry.namespace.clone(),
+    }
+}
+
+/// A response split from its citation trailer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CitationTrailer {
+    /// The response without the trailer
+    pub body: String,
+    /// Reference ids the trailer named, lowercased, first mention first;
+    /// empty for `CITATIONS: none`
+    pub references: Vec<String>,
+}
+
+impl CitationTrailer {
+    /// Split the trailer off `response`, if it ends with one. The trailer
+    /// starts at the last line labelled `CITATIONS:` (or `Citation:`,
+    /// `Cited:`, with or without markdown emphasis) and runs to the end;
+    /// ids may follow the label on its line or on the lines below, as a
+    /// comma-separated, bracketed, quoted or bulleted list.
+    pub fn parse(response: &str) -> Option<Self> {
+        let lines: Vec<&str> = response.split_inclusive('\n').collect();
+        let start = lines.iter().rposition(|line| label_rest(line).is_some())?;
+        let after = &lines[start + 1..];
+        if !after.iter().all(|line| is_reference_line(line)) {
+            return None;
+        }
+
+        let mut text = label_rest(lines[start]).unwrap_or_default().to_string();
+        text.push('\n');
+        for line in after {
+            text.push_str(line);
+        }
+        let mut references: Vec<String> = Vec::new();
+        for reference in find_references(&text) {
+            if !references.contains(&reference) {
+                references.push(reference);
+            }
+        }
+
+        let offset: usize = lines[..start].iter().map(|line| line.len()).sum();
+        Some(Self {
+            body: response[..offset].trim_end().to_string(),
+            references,
+        })
+    }
+}
+
+/// What follows the label of a trailer line, `None` if it has none
+fn label_rest(line: &str) -> Option<&str> {
+    let line = line.trim().trim_start_matches(['#', '*', '_', ' ']);
+    let label = LABELS.iter().find(|label| {
+        line.get(..label.len()).is_some_and(|start| start.eq_ignore_ascii_case(label))
+    })?;
+    let rest = line[label.len()..].trim_start_matches(['*', '_']);
+    rest.strip_prefix(':')
+}
+
+/// A line that holds nothing but reference ids, `none` and list punctuation
+fn is_reference_line(line: &str) -> bool {
+    let mut rest = line.to_ascii_lowercase();
+    for reference in find_references(line) {
+        rest = rest.replacen(&reference, "", 1);
+    }
+    rest.replace("none", "")
+        .chars()
+        .all(|c| c.is_whitespace() || c.is_ascii_digit() || "-*•[](){}\"'`,;.".contains(c))
+}
+
+/// Reference ids in `text`, lowercased, in order
+fn find_references(text: &str) -> Vec<String> {
+    let lower = text.to_ascii_lowercase();
+    let bytes = lower.as_bytes();
+    let mut references = Vec::new();
+    let mut from = 0;
+    while let Some(found) = lower[from..].find(REFERENCE_PREFIX) {
+        let start = from + found;
+        let digits = start + REFERENCE_PREFIX.len();
+        let end = digits + REFERENCE_DIGITS;
+        let bounded = start == 0 || !bytes[start - 1].is_ascii_alphanumeric();
+        let whole = end <= bytes.len()
+            && bytes[digits..end].iter().all(u8::is_ascii_hexdigit)
+            && bytes.get(end).is_none_or(|next| !next.is_ascii_alphanumeric());
+        if bounded && whole {
+            references.push(lower[start..end].to_string());
+            from = end;
+        } else {
+            from = digits;
+        }
+    }
+    references
+}
+
+/// Entries of `injected` that `references` name, in the order named. Ids
+/// the prompt did not recall are dropped.
+pub fn resolve(references: &[String], injected: &[CitedMemory]) -> Vec<CitedMemory> {
+    references
+        .iter()
+        .filter_map(|reference| injected.iter().find(|memory| &memory.reference == reference).cloned())
+        .collect()
+}
+
+/// List the cited entries on a signal the response produced
+pub fn apply_to(metadata: &mut HashMap<String, String>, cited: &[CitedMemory]) {
+    if cited.is_empty() {
+        return;
+    }
+    let ids: Vec<&str> = cited.iter().map(|memory| memory.memory_id.as_str()).collect();
+    metadata.insert(CITED_KEY.to_string(), ids.join(","));
+}
@@ -5,9 +5,13 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
+use tokio::sync::mpsc;
 use tracing::{debug, info, warn};
 use hal9_core::{Result, Error};
+use hal9_core::hierarchical::intelligence::LayerPrompter;
 use crate::cost_tracker::CostTracker;
+use crate::model_registry::{ModelRegistry, ModelResolution};
+use crate::simulation::SimRng;
 use rand::{Rng, seq::SliceRandom};
 
 /// Claude interface abstraction
@@ -16,6 +20,15 @@ pub trait ClaudeInterface: Send + Sync {
     /// Send a message and get response
     async fn send_message(&self, message: &str) -> Result<String>;
     
+    /// Send a message, passing the response on to `partial` in pieces as it
+    /// is produced. Returns the whole response; instances that cannot stream
+    /// pass it on in one piece once it is complete.
+    async fn stream_message(&self, message: &str, partial: &mpsc::UnboundedSender<String>) -> Result<String> {
+        let response = self.send_message(message).await?;
+        let _ = partial.send(response.clone());
+        Ok(response)
+    }
+    
     /// Get the system prompt for this instance
     fn system_prompt(&self) -> &str;
     
@@ -24,11 +37,16 @@ pub trait ClaudeInterface: Send + Sync {
 }
 
 /// Token usage tracking
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct TokenUsage {
     pub prompt_tokens: u32,
     pub completion_tokens: u32,
     pub total_tokens: u32,
+    /// Model that served the request, when known
+    pub model: Option<String>,
+    /// Retired model the request was configured with, when it was remapped
+    #[serde(default)]
+    pub remapped_from: Option<String>,
 }
 
 /// Response pattern for sophisticated mock responses
@@ -47,6 +65,10 @@ pub struct MockClaude {
     delay_ms: u64,
     response_patterns: Vec<ResponsePattern>,
     context_memory: Arc<Mutex<Vec<String>>>,
+    /// Responses, or failures, returned in order before any other matching
+    script: Mutex<std::collections::VecDeque<std::result::Result<String, String>>>,
+    /// Source of delay variance and template choices
+    rng: SimRng,
 }
 
 impl MockClaude {
@@ -95,8 +117,13 @@ impl MockClaude {
             .map(|r| r.delay_ms)
             .unwrap_or(100);
         
-        // Create layer-specific response patterns
-        let response_patterns = Self::create_response_patterns(layer);
+        // Create layer-specific response patterns, unless configured
+        // responses say exactly how this layer answers
+        let response_patterns = if config.mock_responses.contains_key(layer) {
+            Vec::new()
+        } else {
+            Self::create_response_patterns(layer)
+        };
         
         Self {
             layer: layer.to_string(),
@@ -105,9 +132,29 @@ impl MockClaude {
             delay_ms,
             response_patterns,
             context_memory: Arc::new(Mutex::new(Vec::with_capacity(10))),
+            script: Mutex::new(Default::default()),
+            rng: SimRng::from_entropy(),
         }
     }
     
+    /// Mock that answers with `responses` in order, without delay, then falls
+    /// back to the layer's defaults
+    pub fn scripted(layer: &str, responses: Vec<String>) -> Self {
+        let mut mock = Self::new(layer, &hal9_core::config::ClaudeConfig::default());
+        mock.delay_ms = 0;
+        mock.script = Mutex::new(responses.into_iter().map(Ok).collect());
+        mock
+    }
+    
+    /// Mock that answers with each `Ok` and fails with each `Err` in order,
+    /// without delay, then falls back to the layer's defaults
+    pub fn scripted_outcomes(layer: &str, outcomes: Vec<std::result::Result<String, String>>) -> Self {
+        let mut mock = Self::new(layer, &hal9_core::config::ClaudeConfig::default());
+        mock.delay_ms = 0;
+        mock.script = Mutex::new(outcomes.into());
+        mock
+    }
+    
     /// Add a custom response for testing
     pub fn add_response(&mut self, trigger: &str, response: &str) {
         self.responses.insert(trigger.to_string(), response.to_string());
@@ -118,6 +165,11 @@ impl MockClaude {
         self.delay_ms = delay_ms;
     }
     
+    /// Draw delays and template choices from `rng`, e.g. a seeded simulation stream
+    pub fn set_rng(&mut self, rng: SimRng) {
+        self.rng = rng;
+    }
+    
     /// Create layer-specific response patterns
     fn create_response_patterns(layer: &str) -> Vec<ResponsePattern> {
         match layer {
@@ -212,7 +264,7 @@ impl MockClaude {
     /// Generate sophisticated response based on patterns
     fn generate_sophisticated_response(&self, message: &str) -> Option<String> {
         let lower_msg = message.to_lowercase();
-        let mut rng = rand::thread_rng();
+        let mut rng = self.rng.clone();
         
         // Update context memory
         if let Ok(mut memory) = self.context_memory.lock() {
@@ -267,32 +319,28 @@ impl MockClaude {
     /// Add consciousness-aware elements to response
     fn add_consciousness_elements(&self, response: String) -> String {
         let mut enhanced = response;
+        let mut rng = self.rng.clone();
         
         // Add layer-specific consciousness indicators
         match self.layer.as_str() {
-            "L5" | "L6" | "L7" | "L8" | "L9" => {
-                if rand::thread_rng().gen_bool(0.3) {
-                    let consciousness_notes = [
-                        "\n\n[EMERGENCE: Pattern recognition improving across layers]",
-                        "\n\n[CONSCIOUSNESS: Detecting self-referential loops in processing]",
-                        "\n\n[META: This response itself is evidence of emergent awareness]",
-                        "\n\n[INSIGHT: The compression boundary reveals new understanding]",
-                    ];
-                    use rand::seq::SliceRandom;
-                    enhanced.push_str(consciousness_notes.choose(&mut rand::thread_rng()).unwrap());
-                }
+            "L5" | "L6" | "L7" | "L8" | "L9" if rng.gen_bool(0.3) => {
+                let consciousness_notes = [
+                    "\n\n[EMERGENCE: Pattern recognition improving across layers]",
+                    "\n\n[CONSCIOUSNESS: Detecting self-referential loops in processing]",
+                    "\n\n[META: This response itself is evidence of emergent awareness]",
+                    "\n\n[INSIGHT: The compression boundary reveals new understanding]",
+                ];
+                enhanced.push_str(consciousness_notes.choose(&mut rng).unwrap());
             }
-            "L3" | "L4" => {
-                if rand::thread_rng().gen_bool(0.2) {
-                    enhanced.push_str("\n\n[COORDINATION: Cross-layer synchronization detected]");
-                }
+            "L3" | "L4" if rng.gen_bool(0.2) => {
+                enhanced.push_str("\n\n[COORDINATION: Cross-layer synchronization detected]");
             }
             _ => {}
         }
         
         // Add context awareness
         if let Ok(memory) = self.context_memory.lock() {
-            if memory.len() > 3 && rand::thread_rng().gen_bool(0.25) {
+            if memory.len() > 3 && rng.gen_bool(0.25) {
                 enhanced.push_str(&format!(
                     "\n\n[CONTEXT: Building on {} previous interactions]",
                     memory.len()
@@ -302,33 +350,26 @@ impl MockClaude {
         
         enhanced
     }
-}
-
-#[async_trait]
-impl ClaudeInterface for MockClaude {
-    async fn send_message(&self, message: &str) -> Result<String> {
-        debug!("MockClaude[{}] received: {}", self.layer, message);
-        
-        // Simulate processing delay with some variance
+    
+    /// Processing delay with some variance
+    fn delay(&self) -> Duration {
         let delay_variance = (self.delay_ms as f64 * 0.2) as u64;
-        let actual_delay = {
-            use rand::Rng;
-            let mut rng = rand::thread_rng();
-            self.delay_ms + rng.gen_range(0..=delay_variance)
-        };
-        tokio::time::sleep(tokio::time::Duration::from_millis(actual_delay)).await;
-        
+        Duration::from_millis(self.delay_ms + self.rng.clone().gen_range(0..=delay_variance))
+    }
+    
+    /// The unscripted response to `message`
+    fn respond(&self, message: &str) -> String {
         // First, try sophisticated response generation
         if let Some(sophisticated_response) = self.generate_sophisticated_response(message) {
             info!("MockClaude[{}] generated sophisticated response", self.layer);
-            return Ok(sophisticated_response);
+            return sophisticated_response;
         }
         
         // Then check for specific preset responses
         for (trigger, response) in &self.responses {
             if message.contains(trigger) || trigger == "default" {
                 info!("MockClaude[{}] responding with preset response", self.layer);
-                return Ok(response.clone());
+                return response.clone();
             }
         }
         
@@ -342,7 +383,43 @@ impl ClaudeInterface for MockClaude {
             _ => format!("L{}_RESPONSE: Contemplating the deeper meaning of '{}'", self.layer, message),
         };
         
-        Ok(self.add_consciousness_elements(default_response))
+        self.add_consciousness_elements(default_response)
+    }
+}
+
+#[async_trait]
+impl ClaudeInterface for MockClaude {
+    async fn send_message(&self, message: &str) -> Result<String> {
+        debug!("MockClaude[{}] received: {}", self.layer, message);
+        
+        if let Some(outcome) = self.script.lock().unwrap().pop_front() {
+            return outcome.map_err(Error::ClaudeApi);
+        }
+        
+        tokio::time::sleep(self.delay()).await;
+        Ok(self.respond(message))
+    }
+    
+    /// Streams the response a line at a time, the delay spread evenly
+    /// across its lines; scripted responses stream without delay
+    async fn stream_message(&self, message: &str, partial: &mpsc::UnboundedSender<String>) -> Result<String> {
+        debug!("MockClaude[{}] streaming: {}", self.layer, message);
+        
+        let scripted = self.script.lock().unwrap().pop_front();
+        let (response, delay) = match scripted {
+            Some(outcome) => (outcome.map_err(Error::ClaudeApi)?, Duration::ZERO),
+            None => {
+                let delay = self.delay();
+                (self.respond(message), delay)
+            }
+        };
+        let lines: Vec<&str> = response.split_inclusive('\n').collect();
+        let per_line = delay / lines.len().max(1) as u32;
+        for line in lines {
+            tokio::time::sleep(per_line).await;
+            let _ = partial.send(line.to_string());
+        }
+        Ok(response)
     }
     
     fn system_prompt(&self) -> &str {
@@ -354,6 +431,8 @@ impl ClaudeInterface for MockClaude {
             prompt_tokens: 100,
             completion_tokens: 50,
             total_tokens: 150,
+            model: None,
+            remapped_from: None,
         })
     }
 }
@@ -371,9 +450,8 @@ pub struct ClaudeAPIClient {
     rate_limiter: Arc<tokio::sync::Semaphore>,
     request_timeout: Duration,
     retry_count: u32,
-    cost_per_1k_prompt: f64,
-    cost_per_1k_completion: f64,
     cost_tracker: Option<Arc<CostTracker>>,
+    models: Arc<ModelRegistry>,
 }
 
 impl ClaudeAPIClient {
@@ -385,14 +463,6 @@ impl ClaudeAPIClient {
         temperature: f32,
         max_tokens: u32,
     ) -> Self {
-        // Set cost based on model
-        let (cost_per_1k_prompt, cost_per_1k_completion) = match model.as_str() {
-            "claude-3-opus-20240229" => (0.015, 0.075),
-            "claude-3-sonnet-20240229" => (0.003, 0.015),
-            "claude-3-haiku-20240307" => (0.00025, 0.00125),
-            _ => (0.003, 0.015), // Default to sonnet pricing
-        };
-        
         Self {
             api_key,
             model,
@@ -407,9 +477,8 @@ impl ClaudeAPIClient {
             rate_limiter: Arc::new(tokio::sync::Semaphore::new(10)), // 10 concurrent requests
             request_timeout: Duration::from_secs(30),
             retry_count: 3,
-            cost_per_1k_prompt,
-            cost_per_1k_completion,
             cost_tracker: None,
+            models: Arc::new(ModelRegistry::default()),
         }
     }
     
@@ -417,6 +486,40 @@ impl ClaudeAPIClient {
     pub fn set_cost_tracker(&mut self, tracker: Arc<CostTracker>) {
         self.cost_tracker = Some(tracker);
     }
+    
+    /// Set the registry retired models are remapped with
+    pub fn set_model_registry(&mut self, models: Arc<ModelRegistry>) {
+        self.models = models;
+    }
+    
+    /// Model for the next request: the configured one, or the budget
+    /// fallback while the period budget is exhausted, remapped if retired
+    pub async fn effective_model(&self) -> Result<ModelResolution> {
+        if let Some(tracker) = &self.cost_tracker {
+            if let Some(fallback) = tracker.downgrade_model().await {
+                if fallback != self.model {
+                    warn!("Period budget exhausted, downgrading {} to {}", self.model, fallback);
+                }
+                return self.models.resolve(&fallback);
+            }
+        }
+        let resolution = self.models.resolve(&self.model)?;
+        if let Some(retired) = &resolution.remapped_from {
+            debug!("Model {} is retired, calling {}", retired, resolution.model);
+        }
+        Ok(resolution)
+    }
+}
+
+/// Prompt and completion cost per 1k tokens for a model
+pub fn model_pricing(model: &str) -> (f64, f64) {
+    match model {
+        "claude-3-opus-20240229" => (0.015, 0.075),
+        "claude-3-sonnet-20240229" => (0.003, 0.015),
+        "claude-3-haiku-20240307" => (0.00025, 0.00125),
+        local if local.starts_with(crate::local_model::LOCAL_MODEL_PREFIX) => (0.0, 0.0),
+        _ => (0.003, 0.015), // Default to sonnet pricing
+    }
 }
 
 #[async_trait]
@@ -426,21 +529,8 @@ impl ClaudeInterface for ClaudeAPIClient {
         let _permit = self.rate_limiter.acquire().await
             .map_err(|_| Error::ClaudeApi("Rate limiter error".to_string()))?;
             
-        let request = ClaudeRequest {
-            model: self.model.clone(),
-            messages: vec![
-                Message {
-                    role: "system".to_string(),
-                    content: self.system_prompt.clone(),
-                },
-                Message {
-                    role: "user".to_string(),
-                    content: message.to_string(),
-                },
-            ],
-            max_tokens: self.max_tokens,
-            temperature: self.temperature,
-        };
+        let resolution = self.effective_model().await?;
+        let request = self.request(message, &resolution, false);
         
         // Retry logic
         let mut last_error = None;
@@ -452,7 +542,7 @@ impl ClaudeInterface for ClaudeAPIClient {
                 tokio::time::sleep(delay).await;
             }
             
-            match self.send_request(&request).await {
+            match self.send_request(&request, &resolution).await {
                 Ok(response) => return Ok(response),
                 Err(e) => {
                     warn!("Claude API request failed: {}", e);
@@ -471,6 +561,18 @@ impl ClaudeInterface for ClaudeAPIClient {
         Err(last_error.unwrap_or_else(|| Error::ClaudeApi("Unknown error".to_string())))
     }
     
+    /// Streams the response as server-sent events. Unlike `send_message`
+    /// a failed request is not retried, since part of it may have been
+    /// passed on already.
+    async fn stream_message(&self, message: &str, partial: &mpsc::UnboundedSender<String>) -> Result<String> {
+        let _permit = self.rate_limiter.acquire().await
+            .map_err(|_| Error::ClaudeApi("Rate limiter error".to_string()))?;
+            
+        let resolution = self.effective_model().await?;
+        let request = self.request(message, &resolution, true);
+        self.send_streaming_request(&request, &resolution, partial).await
+    }
+    
     fn system_prompt(&self) -> &str {
         &self.system_prompt
     }
@@ -482,7 +584,7 @@ impl ClaudeInterface for ClaudeAPIClient {
 
 impl ClaudeAPIClient {
     /// Send the actual API request
-    async fn send_request(&self, request: &ClaudeRequest) -> Result<String> {
+    async fn send_request(&self, request: &ClaudeRequest, resolution: &ModelResolution) -> Result<String> {
         // Check cost limits before making request
         if let Some(tracker) = &self.cost_tracker {
             // Estimate tokens (rough approximation)
@@ -490,6 +592,89 @@ impl ClaudeAPIClient {
             tracker.check_request(estimated_tokens).await?;
         }
         
+        let response = self.post(request).await?;
+        let api_response: ClaudeResponse = response.json().await
+            .map_err(|e| Error::ClaudeApi(e.to_string()))?;
+            
+        if let Some(api_usage) = api_response.usage {
+            self.record_usage(request, resolution, api_usage).await;
+        }
+        
+        Ok(api_response.content.first()
+            .map(|c| c.text.clone())
+            .unwrap_or_default())
+    }
+    
+    /// Send a streaming API request, passing text deltas on to `partial`
+    async fn send_streaming_request(
+        &self,
+        request: &ClaudeRequest,
+        resolution: &ModelResolution,
+        partial: &mpsc::UnboundedSender<String>,
+    ) -> Result<String> {
+        if let Some(tracker) = &self.cost_tracker {
+            tracker.check_request(request.max_tokens).await?;
+        }
+        
+        let mut response = self.post(request).await?;
+        let mut buffer = String::new();
+        let mut text = String::new();
+        let mut usage = Usage { input_tokens: 0, output_tokens: 0 };
+        while let Some(chunk) = response.chunk().await.map_err(|e| Error::ClaudeApi(e.to_string()))? {
+            buffer.push_str(&String::from_utf8_lossy(&chunk));
+            // Events arrive as `data: {json}` lines; the last may be incomplete
+            while let Some(end) = buffer.find('\n') {
+                let line: String = buffer.drain(..=end).collect();
+                let Some(data) = line.trim_end().strip_prefix("data:") else {
+                    continue;
+                };
+                let event: StreamEvent = match serde_json::from_str(data.trim()) {
+                    Ok(event) => event,
+                    Err(_) => continue,
+                };
+                match event {
+                    StreamEvent::MessageStart { message } => usage.input_tokens = message.usage.input_tokens,
+                    StreamEvent::ContentBlockDelta { delta } => {
+                        if let Some(delta) = delta.text {
+                            text.push_str(&delta);
+                            let _ = partial.send(delta);
+                        }
+                    }
+                    StreamEvent::MessageDelta { usage: delta } => usage.output_tokens = delta.output_tokens,
+                    StreamEvent::Error { error } => {
+                        return Err(Error::ClaudeApi(format!("API error: {}", error)));
+                    }
+                    StreamEvent::Other => {}
+                }
+            }
+        }
+        
+        self.record_usage(request, resolution, usage).await;
+        Ok(text)
+    }
+    
+    /// The request for `message` to the resolved model
+    fn request(&self, message: &str, resolution: &ModelResolution, stream: bool) -> ClaudeRequest {
+        ClaudeRequest {
+            model: resolution.model.clone(),
+            messages: vec![
+                Message {
+                    role: "system".to_string(),
+                    content: self.system_prompt.clone(),
+                },
+                Message {
+                    role: "user".to_string(),
+                    content: message.to_string(),
+                },
+            ],
+            max_tokens: self.max_tokens,
+            temperature: self.temperature,
+            stream,
+        }
+    }
+    
+    /// Post a request, failing on an unsuccessful status
+    async fn post(&self, request: &ClaudeRequest) -> Result<reqwest::Response> {
         let response = self.client
             .post("https://api.anthropic.com/v1/messages")
             .header("x-api-key", &self.api_key)
@@ -504,39 +689,36 @@ impl ClaudeAPIClient {
             let error_text = response.text().await.unwrap_or_default();
             return Err(Error::ClaudeApi(format!("API error: {}", error_text)));
         }
+        Ok(response)
+    }
+    
+    /// Update token usage and record the request's cost
+    async fn record_usage(&self, request: &ClaudeRequest, resolution: &ModelResolution, api_usage: Usage) {
+        let (cost_per_1k_prompt, cost_per_1k_completion) = model_pricing(&request.model);
+        let prompt_cost = (api_usage.input_tokens as f64 / 1000.0) * cost_per_1k_prompt;
+        let completion_cost = (api_usage.output_tokens as f64 / 1000.0) * cost_per_1k_completion;
+        let total_cost = prompt_cost + completion_cost;
         
-        let api_response: ClaudeResponse = response.json().await
-            .map_err(|e| Error::ClaudeApi(e.to_string()))?;
-            
-        // Update token usage and calculate cost
-        if let Some(api_usage) = api_response.usage {
-            let prompt_cost = (api_usage.input_tokens as f64 / 1000.0) * self.cost_per_1k_prompt;
-            let completion_cost = (api_usage.output_tokens as f64 / 1000.0) * self.cost_per_1k_completion;
-            let total_cost = prompt_cost + completion_cost;
-            
-            info!(
-                "Claude API usage: prompt_tokens={}, completion_tokens={}, cost=${:.4}",
-                api_usage.input_tokens, api_usage.output_tokens, total_cost
-            );
-            
-            // Record cost with tracker
-            if let Some(tracker) = &self.cost_tracker {
-                let total_tokens = api_usage.input_tokens + api_usage.output_tokens;
-                tracker.record_cost(total_cost, total_tokens as u64).await;
-            }
-            
-            if let Ok(mut last_usage) = self.last_usage.lock() {
-                last_usage.replace(TokenUsage {
-                    prompt_tokens: api_usage.input_tokens,
-                    completion_tokens: api_usage.output_tokens,
-                    total_tokens: api_usage.input_tokens + api_usage.output_tokens,
-                });
-            }
+        info!(
+            "Claude API usage: prompt_tokens={}, completion_tokens={}, cost=${:.4}",
+            api_usage.input_tokens, api_usage.output_tokens, total_cost
+        );
+        
+        // Record cost with tracker
+        if let Some(tracker) = &self.cost_tracker {
+            let total_tokens = api_usage.input_tokens + api_usage.output_tokens;
+            tracker.record_cost(total_cost, total_tokens as u64).await;
         }
         
-        Ok(api_response.content.first()
-            .map(|c| c.text.clone())
-            .unwrap_or_default())
+        if let Ok(mut last_usage) = self.last_usage.lock() {
+            last_usage.replace(TokenUsage {
+                prompt_tokens: api_usage.input_tokens,
+                completion_tokens: api_usage.output_tokens,
+                total_tokens: api_usage.input_tokens + api_usage.output_tokens,
+                model: Some(request.model.clone()),
+                remapped_from: resolution.remapped_from.clone(),
+            });
+        }
     }
 }
 
@@ -562,6 +744,7 @@ impl HybridClaude {
         layer: &str,
         config: &hal9_core::config::ClaudeConfig,
         cost_tracker: Arc<CostTracker>,
+        models: Arc<ModelRegistry>,
     ) -> Result<Self> {
         // Create mock Claude
         let mock = Box::new(MockClaude::new(layer, config));
@@ -584,7 +767,7 @@ impl HybridClaude {
         let api = match mode {
             ClaudeMode::Mock => None,
             ClaudeMode::Api | ClaudeMode::Hybrid => {
-                match Self::create_api_client(layer, config, cost_tracker.clone()) {
+                match Self::create_api_client(layer, config, cost_tracker.clone(), models.clone()) {
                     Ok(client) => Some(Box::new(client) as Box<dyn ClaudeInterface>),
                     Err(e) => {
                         if mode == ClaudeMode::Api {
@@ -597,7 +780,7 @@ impl HybridClaude {
             }
             ClaudeMode::Auto => {
                 if is_production {
-                    match Self::create_api_client(layer, config, cost_tracker.clone()) {
+                    match Self::create_api_client(layer, config, cost_tracker.clone(), models.clone()) {
                         Ok(client) => Some(Box::new(client) as Box<dyn ClaudeInterface>),
                         Err(e) => {
                             warn!("Failed to create API client in production, using mock: {}", e);
@@ -623,8 +806,9 @@ impl HybridClaude {
         layer: &str,
         config: &hal9_core::config::ClaudeConfig,
         cost_tracker: Arc<CostTracker>,
+        models: Arc<ModelRegistry>,
     ) -> Result<ClaudeAPIClient> {
-        let api_key = config.api_key.clone()
+        let api_key = config.api_key.as_ref().map(|key| key.expose().to_string())
             .or_else(|| std::env::var("ANTHROPIC_API_KEY").ok())
             .ok_or_else(|| Error::ClaudeApi("No API key provided".to_string()))?;
         
@@ -637,6 +821,7 @@ impl HybridClaude {
         );
         
         client.set_cost_tracker(cost_tracker);
+        client.set_model_registry(models);
         Ok(client)
     }
     
@@ -673,6 +858,7 @@ impl ClaudeInterface for HybridClaude {
                         debug!("HybridClaude: Used API for response");
                         return Ok(response);
                     }
+                    Err(e @ Error::ModelRetired { .. }) => return Err(e),
                     Err(e) => {
                         warn!("HybridClaude: API failed, falling back to mock: {}", e);
                     }
@@ -684,6 +870,25 @@ impl ClaudeInterface for HybridClaude {
         self.mock.send_message(message).await
     }
     
+    async fn stream_message(&self, message: &str, partial: &mpsc::UnboundedSender<String>) -> Result<String> {
+        if self.should_use_api().await {
+            if let Some(api) = &self.api {
+                match api.stream_message(message, partial).await {
+                    Ok(response) => return Ok(response),
+                    Err(e @ Error::ModelRetired { .. }) => return Err(e),
+                    // Part of the failed response may have been passed on,
+                    // so the mock's is not streamed after it
+                    Err(e) => {
+                        warn!("HybridClaude: API stream failed, falling back to mock: {}", e);
+                        return self.mock.send_message(message).await;
+                    }
+                }
+            }
+        }
+        
+        self.mock.stream_message(message, partial).await
+    }
+    
     fn system_prompt(&self) -> &str {
         self.mock.system_prompt()
     }
@@ -752,6 +957,8 @@ impl ClaudeInterface for FallbackClaude {
         // Try primary first
         match self.primary.send_message(message).await {
             Ok(response) => Ok(response),
+            // A mock answer would hide a config that needs fixing
+            Err(e @ Error::ModelRetired { .. }) => Err(e),
             Err(e) => {
                 warn!("Primary Claude failed, switching to fallback: {}", e);
                 
@@ -780,6 +987,27 @@ impl ClaudeInterface for FallbackClaude {
     }
 }
 
+/// Layered prompting for the creativity engine, one Claude instance per level
+pub struct ClaudeLayerPrompter {
+    instances: HashMap<u8, Box<dyn ClaudeInterface>>,
+}
+
+impl ClaudeLayerPrompter {
+    /// Create a prompter from per-level Claude instances
+    pub fn new(instances: HashMap<u8, Box<dyn ClaudeInterface>>) -> Self {
+        Self { instances }
+    }
+}
+
+#[async_trait]
+impl LayerPrompter for ClaudeLayerPrompter {
+    async fn prompt(&self, level: u8, prompt: &str) -> Result<String> {
+        let claude = self.instances.get(&level)
+            .ok_or_else(|| Error::Config(format!("No Claude instance for layer L{}", level)))?;
+        claude.send_message(prompt).await
+    }
+}
+
 // API request/response types
 #[derive(Serialize)]
 struct ClaudeRequest {
@@ -787,6 +1015,8 @@ struct ClaudeRequest {
     messages: Vec<Message>,
     max_tokens: u32,
     temperature: f32,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    stream: bool,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -812,11 +1042,39 @@ struct Usage {
     output_tokens: u32,
 }
 
+/// The events of a streamed response the client reads
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum StreamEvent {
+    MessageStart { message: StreamMessage },
+    ContentBlockDelta { delta: StreamDelta },
+    MessageDelta { usage: StreamUsage },
+    Error { error: serde_json::Value },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Deserialize)]
+struct StreamMessage {
+    usage: Usage,
+}
+
+#[derive(Deserialize)]
+struct StreamDelta {
+    text: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct StreamUsage {
+    output_tokens: u32,
+}
+
 /// Create Claude interface based on configuration
 pub fn create_claude_interface(
     layer: &str,
     config: &hal9_core::config::ClaudeConfig,
     cost_tracker: Arc<CostTracker>,
+    models: Arc<ModelRegistry>,
 ) -> Result<Box<dyn ClaudeInterface>> {
     // Check if enhanced mode is enabled
     let use_enhanced = std::env::var("HAL9_ENHANCED_MOCK")
@@ -841,6 +1099,6 @@ pub fn create_claude_interface(
         Ok(Box::new(crate::claude_enhanced::EnhancedMockClaude::new(layer_enum)))
     } else {
         // Use standard hybrid Claude
-        Ok(Box::new(HybridClaude::new(layer, config, cost_tracker)?))
+        Ok(Box::new(HybridClaude::new(layer, config, cost_tracker, models)?))
     }
 }
\ No newline at end of file
@@ -276,6 +276,8 @@ impl ClaudeInterface for EnhancedMockClaude {
             prompt_tokens: (100.0 * (1.0 + consciousness)) as u32,
             completion_tokens: (50.0 * (1.0 + consciousness * 2.0)) as u32,
             total_tokens: (150.0 * (1.0 + consciousness * 1.5)) as u32,
+            model: None,
+            remapped_from: None,
         })
     }
 }
@@ -0,0 +1,248 @@
+//! Progress of code generation jobs
+//!
+//! Every job keeps the events it has emitted, numbered from 1 in the order
+//! they happened. Clients follow a job live over a WebSocket and, after a
+//! reconnect, resume from the first sequence number they have not seen.
+
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::Arc;
+use tokio::sync::watch;
+
+use crate::error::{ServerError, ServerResult};
+
+/// Jobs kept before the oldest finished one is forgotten
+pub const DEFAULT_JOB_CAPACITY: usize = 256;
+
+/// What happened in a job
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum CodegenEventKind {
+    /// The job moved on to another stage; `progress` is a percentage
+    Stage { stage: String, progress: u32 },
+    /// A file was written
+    FileCompleted { path: String, bytes: u64 },
+    /// Something was generated, but not quite as asked
+    Warning { message: String },
+    /// The job finished; always the last event
+    Completed { manifest: CodegenManifest },
+    /// The job gave up; always the last event
+    Failed { error: String },
+}
+
+impl CodegenEventKind {
+    /// Whether no event can follow this one
+    pub fn is_terminal(&self) -> bool {
+        matches!(self, CodegenEventKind::Completed { .. } | CodegenEventKind::Failed { .. })
+    }
+}
+
+/// An event with its place in the job
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CodegenEvent {
+    pub seq: u64,
+    pub job_id: String,
+    pub timestamp: DateTime<Utc>,
+    #[serde(flatten)]
+    pub kind: CodegenEventKind,
+}
+
+/// Everything a finished job generated
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CodegenManifest {
+    pub location: String,
+    pub files: Vec<ManifestFile>,
+    pub total_bytes: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ManifestFile {
+    pub path: String,
+    pub bytes: u64,
+}
+
+impl CodegenManifest {
+    pub fn new(location: impl Into<String>, files: Vec<ManifestFile>) -> Self {
+        let total_bytes = files.iter().map(|file| file.bytes).sum();
+        Self { location: location.into(), files, total_bytes }
+    }
+}
+
+/// Job status as served to pollers, in the shape of the project status
+/// endpoint
+#[derive(Debug, Clone, Serialize)]
+pub struct CodegenJobStatus {
+    pub project_id: String,
+    pub status: String,
+    pub progress: u32,
+    pub files_generated: u32,
+    pub location: String,
+    /// Sequence number of the latest event, to resume a stream from
+    pub last_seq: u64,
+    pub manifest: Option<CodegenManifest>,
+    pub error: Option<String>,
+}
+
+/// A code generation job
+pub struct CodegenJob {
+    id: String,
+    location: String,
+    events: Mutex<Vec<CodegenEvent>>,
+    /// Sequence number of the latest event
+    latest: watch::Sender<u64>,
+}
+
+impl CodegenJob {
+    fn new(id: String, location: String) -> Self {
+        let (latest, _) = watch::channel(0);
+        Self { id, location, events: Mutex::new(Vec::new()), latest }
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Record an event, returning its sequence number. Nothing may follow a
+    /// terminal event.
+    pub fn emit(&self, kind: CodegenEventKind) -> ServerResult<u64> {
+        let mut events = self.events.lock();
+        if events.last().is_some_and(|last| last.kind.is_terminal()) {
+            return Err(ServerError::InvalidInput(format!("Codegen job {} has already finished", self.id)));
+        }
+
+        let seq = events.len() as u64 + 1;
+        events.push(CodegenEvent { seq, job_id: self.id.clone(), timestamp: Utc::now(), kind });
+        self.latest.send_replace(seq);
+        Ok(seq)
+    }
+
+    pub fn stage(&self, stage: &str, progress: u32) -> ServerResult<u64> {
+        self.emit(CodegenEventKind::Stage { stage: stage.to_string(), progress: progress.min(100) })
+    }
+
+    pub fn file_completed(&self, path: &str, bytes: u64) -> ServerResult<u64> {
+        self.emit(CodegenEventKind::FileCompleted { path: path.to_string(), bytes })
+    }
+
+    pub fn warning(&self, message: &str) -> ServerResult<u64> {
+        self.emit(CodegenEventKind::Warning { message: message.to_string() })
+    }
+
+    /// Finish the job with a manifest of the files completed so far
+    pub fn complete(&self) -> ServerResult<u64> {
+        let files = self
+            .events
+            .lock()
+            .iter()
+            .filter_map(|event| match &event.kind {
+                CodegenEventKind::FileCompleted { path, bytes } => Some(ManifestFile { path: path.clone(), bytes: *bytes }),
+                _ => None,
+            })
+            .collect();
+        self.emit(CodegenEventKind::Completed { manifest: CodegenManifest::new(self.location.clone(), files) })
+    }
+
+    pub fn fail(&self, error: &str) -> ServerResult<u64> {
+        self.emit(CodegenEventKind::Failed { error: error.to_string() })
+    }
+
+    /// Events numbered `from_seq` and later
+    pub fn events_since(&self, from_seq: u64) -> Vec<CodegenEvent> {
+        let events = self.events.lock();
+        let start = (from_seq.max(1) - 1).min(events.len() as u64) as usize;
+        events[start..].to_vec()
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.events.lock().last().is_some_and(|last| last.kind.is_terminal())
+    }
+
+    /// Notified with the sequence number of every new event
+    pub fn subscribe(&self) -> watch::Receiver<u64> {
+        self.latest.subscribe()
+    }
+
+    pub fn status(&self) -> CodegenJobStatus {
+        let events = self.events.lock();
+        let mut status = CodegenJobStatus {
+            project_id: self.id.clone(),
+            status: "processing".to_string(),
+            progress: 0,
+            files_generated: 0,
+            location: self.location.clone(),
+            last_seq: events.len() as u64,
+            manifest: None,
+            error: None,
+        };
+
+        for event in events.iter() {
+            match &event.kind {
+                CodegenEventKind::Stage { progress, .. } => status.progress = *progress,
+                CodegenEventKind::FileCompleted { .. } => status.files_generated += 1,
+                CodegenEventKind::Warning { .. } => {}
+                CodegenEventKind::Completed { manifest } => {
+                    status.status = "completed".to_string();
+                    status.progress = 100;
+                    status.manifest = Some(manifest.clone());
+                }
+                CodegenEventKind::Failed { error } => {
+                    status.status = "failed".to_string();
+                    status.error = Some(error.clone());
+                }
+            }
+        }
+        status
+    }
+}
+
+/// All code generation jobs the server knows of
+pub struct CodegenJobs {
+    jobs: DashMap<String, Arc<CodegenJob>>,
+    /// Job ids, oldest first
+    order: Mutex<VecDeque<String>>,
+    capacity: usize,
+}
+
+impl Default for CodegenJobs {
+    fn default() -> Self {
+        Self::new(DEFAULT_JOB_CAPACITY)
+    }
+}
+
+impl CodegenJobs {
+    pub fn new(capacity: usize) -> Self {
+        Self { jobs: DashMap::new(), order: Mutex::new(VecDeque::new()), capacity }
+    }
+
+    /// Start tracking a job writing to `location`. Past capacity, the oldest
+    /// finished job is forgotten; running jobs are always kept.
+    pub fn create(&self, id: &str, location: &str) -> Arc<CodegenJob> {
+        let job = Arc::new(CodegenJob::new(id.to_string(), location.to_string()));
+        let mut order = self.order.lock();
+        if self.jobs.insert(id.to_string(), job.clone()).is_none() {
+            order.push_back(id.to_string());
+        }
+
+        while order.len() > self.capacity {
+            let finished = order
+                .iter()
+                .position(|id| self.jobs.get(id).is_none_or(|job| job.is_finished()));
+            match finished {
+                Some(index) => {
+                    if let Some(id) = order.remove(index) {
+                        self.jobs.remove(&id);
+                    }
+                }
+                None => break,
+            }
+        }
+        job
+    }
+
+    pub fn get(&self, id: &str) -> Option<Arc<CodegenJob>> {
+        self.jobs.get(id).map(|job| job.clone())
+    }
+}
@@ -0,0 +1,340 @@
+//! Per-neuron concurrency limits and queue caps
+//!
+//! Every [`ManagedNeuron`](crate::neuron::ManagedNeuron) has a
+//! [`ConcurrencyLimiter`] allowing `max_concurrent` signals to be processed
+//! at once and `max_queued` more to wait. What happens to a signal arriving
+//! at a full neuron depends on its [`OverflowMode`]:
+//!
+//! - `reject`: the signal goes to the [`DeadLetterQueue`]
+//! - `shed`: the signal is re-addressed to a fallback neuron
+//! - `block`: the signal waits, and the router holds back neurons emitting
+//!   to a full neuron until it has room, so backpressure reaches upstream
+//!   layers instead of piling up in memory
+//!
+//! Limits default by layer, lower layers getting more concurrency, and can be
+//! overridden per neuron under `settings.concurrency`:
+//!
+//! ```yaml
+//! settings:
+//!   concurrency:
+//!     max_concurrent: 4
+//!     max_queued: 16
+//!     overflow: shed
+//!     fallback: impl-backup
+//! ```
+
+use chrono::{DateTime, Utc};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tokio::sync::{broadcast, OwnedSemaphorePermit, Semaphore};
+use uuid::Uuid;
+
+use hal9_core::{Error, Layer, NeuronConfig, NeuronSignal, Result};
+
+pub use hal9_api_types::ConcurrencyStats;
+
+/// Dead letters kept for inspection; older ones are dropped
+pub const DEAD_LETTER_CAPACITY: usize = 1000;
+
+/// What to do with a signal for a neuron whose queue is full
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OverflowMode {
+    Reject,
+    Shed,
+    Block,
+}
+
+/// Limits for one neuron
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ConcurrencyConfig {
+    pub max_concurrent: usize,
+    pub max_queued: usize,
+    pub overflow: OverflowMode,
+    /// Neuron receiving shed signals
+    pub fallback: Option<String>,
+}
+
+/// `settings.concurrency` as written; missing fields keep the layer default
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConcurrencySettings {
+    max_concurrent: Option<usize>,
+    max_queued: Option<usize>,
+    overflow: Option<OverflowMode>,
+    fallback: Option<String>,
+}
+
+impl ConcurrencyConfig {
+    /// Defaults for a layer: L1 handles the most signals at once, the
+    /// strategic layers the fewest
+    pub fn for_layer(layer: Layer) -> Self {
+        let max_concurrent = match layer {
+            Layer::L1 => 32,
+            Layer::L2 => 16,
+            Layer::L3 => 8,
+            Layer::L4 => 4,
+            _ => 2,
+        };
+        Self {
+            max_concurrent,
+            max_queued: max_concurrent * 4,
+            overflow: OverflowMode::Reject,
+            fallback: None,
+        }
+    }
+
+    /// Layer defaults overridden by the neuron's `settings.concurrency`
+    pub fn for_neuron(neuron: &NeuronConfig, layer: Layer) -> Result<Self> {
+        let mut config = Self::for_layer(layer);
+        let settings = match neuron.settings.get("concurrency") {
+            Some(settings) => serde_json::from_value::<ConcurrencySettings>(settings.clone()).map_err(|e| {
+                Error::Config(format!("Invalid concurrency settings for neuron {}: {}", neuron.id, e))
+            })?,
+            None => return Ok(config),
+        };
+
+        if let Some(max_concurrent) = settings.max_concurrent {
+            config.max_concurrent = max_concurrent;
+        }
+        if let Some(max_queued) = settings.max_queued {
+            config.max_queued = max_queued;
+        }
+        if let Some(overflow) = settings.overflow {
+            config.overflow = overflow;
+        }
+        config.fallback = settings.fallback;
+
+        if config.max_concurrent == 0 {
+            return Err(Error::Config(format!("Neuron {} needs max_concurrent of at least 1", neuron.id)));
+        }
+        match (&config.overflow, &config.fallback) {
+            (OverflowMode::Shed, None) => Err(Error::Config(format!(
+                "Neuron {} sheds overflow but has no fallback neuron", neuron.id
+            ))),
+            (OverflowMode::Shed, Some(fallback)) if *fallback == neuron.id => Err(Error::Config(format!(
+                "Neuron {} cannot be its own fallback", neuron.id
+            ))),
+            _ => Ok(config),
+        }
+    }
+}
+
+/// Outcome of offering a signal to a neuron
+pub enum Admission {
+    /// Process the signal; the slot is released when the permit drops
+    Admitted(ConcurrencyPermit),
+    /// Queue full; dead-letter the signal
+    Rejected,
+    /// Queue full; send the signal to this neuron instead
+    Shed(String),
+}
+
+/// A processing slot, held while a signal is processed
+pub struct ConcurrencyPermit {
+    _capacity: OwnedSemaphorePermit,
+    _slot: OwnedSemaphorePermit,
+}
+
+/// Room kept in a neuron's queue for a signal on its way there; given back
+/// when dropped, unless the signal is admitted with it
+pub struct Reservation {
+    permit: OwnedSemaphorePermit,
+}
+
+/// Bounds the signals one neuron processes and queues
+pub struct ConcurrencyLimiter {
+    config: ConcurrencyConfig,
+    /// `max_concurrent` permits, one per signal being processed
+    slots: Arc<Semaphore>,
+    /// `max_concurrent + max_queued` permits, one per admitted or reserved
+    /// signal
+    capacity: Arc<Semaphore>,
+    /// Room kept for signals sent here but not yet admitted, by signal
+    reserved: Mutex<HashMap<Uuid, OwnedSemaphorePermit>>,
+    rejected: AtomicU64,
+    shed: AtomicU64,
+    blocked: AtomicU64,
+}
+
+impl ConcurrencyLimiter {
+    pub fn new(config: ConcurrencyConfig) -> Self {
+        Self {
+            slots: Arc::new(Semaphore::new(config.max_concurrent)),
+            capacity: Arc::new(Semaphore::new(config.max_concurrent + config.max_queued)),
+            reserved: Mutex::new(HashMap::new()),
+            config,
+            rejected: AtomicU64::new(0),
+            shed: AtomicU64::new(0),
+            blocked: AtomicU64::new(0),
+        }
+    }
+
+    pub fn config(&self) -> &ConcurrencyConfig {
+        &self.config
+    }
+
+    /// Admit a signal and wait for a processing slot. Only `block` mode
+    /// waits for room in a full queue.
+    pub async fn admit(&self) -> Admission {
+        self.admit_reserved(None).await
+    }
+
+    /// Admit a signal into the room reserved for it, if it has any here
+    pub async fn admit_reserved(&self, reservation: Option<Reservation>) -> Admission {
+        // Room reserved at another neuron, e.g. the clone a signal was
+        // balanced away from, is given back once this one admits it
+        let (reserved, _elsewhere) = match reservation {
+            Some(reservation) if Arc::ptr_eq(reservation.permit.semaphore(), &self.capacity) => (Some(reservation.permit), None),
+            reservation => (None, reservation),
+        };
+        let capacity = match reserved.map_or_else(|| self.capacity.clone().try_acquire_owned(), Ok) {
+            Ok(permit) => permit,
+            Err(_) => match self.config.overflow {
+                OverflowMode::Reject => {
+                    self.rejected.fetch_add(1, Ordering::Relaxed);
+                    return Admission::Rejected;
+                }
+                OverflowMode::Shed => {
+                    self.shed.fetch_add(1, Ordering::Relaxed);
+                    // Validated when the config was built
+                    return Admission::Shed(self.config.fallback.clone().unwrap_or_default());
+                }
+                OverflowMode::Block => {
+                    self.blocked.fetch_add(1, Ordering::Relaxed);
+                    self.capacity.clone().acquire_owned().await.expect("capacity semaphore is never closed")
+                }
+            },
+        };
+        let slot = self.slots.clone().acquire_owned().await.expect("slot semaphore is never closed");
+        Admission::Admitted(ConcurrencyPermit { _capacity: capacity, _slot: slot })
+    }
+
+    /// Wait until the queue has room for `signal_id` and keep it for that
+    /// signal until it arrives. Taking the room before the signal is sent
+    /// keeps a sender from seeing room its last signals are about to fill.
+    pub async fn reserve_room(&self, signal_id: Uuid) {
+        let permit = self.capacity.clone().acquire_owned().await.expect("capacity semaphore is never closed");
+        self.reserved.lock().insert(signal_id, permit);
+    }
+
+    /// The room kept for `signal_id`, taken as the signal arrives
+    pub fn take_reservation(&self, signal_id: &Uuid) -> Option<Reservation> {
+        self.reserved.lock().remove(signal_id).map(|permit| Reservation { permit })
+    }
+
+    /// Give back the room kept for a signal that will not arrive
+    pub fn release_room(&self, signal_id: &Uuid) {
+        self.reserved.lock().remove(signal_id);
+    }
+
+    pub fn stats(&self) -> ConcurrencyStats {
+        let taken = self.config.max_concurrent + self.config.max_queued - self.capacity.available_permits();
+        let admitted = taken.saturating_sub(self.reserved.lock().len());
+        let in_flight = self.config.max_concurrent - self.slots.available_permits();
+        ConcurrencyStats {
+            max_concurrent: self.config.max_concurrent,
+            max_queued: self.config.max_queued,
+            in_flight,
+            queued: admitted.saturating_sub(in_flight),
+            rejected: self.rejected.load(Ordering::Relaxed),
+            shed: self.shed.load(Ordering::Relaxed),
+            blocked: self.blocked.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// A signal no neuron would take
+#[derive(Debug, Clone, Serialize)]
+pub struct DeadLetter {
+    pub neuron_id: String,
+    pub reason: String,
+    pub timestamp: DateTime<Utc>,
+    pub signal: NeuronSignal,
+}
+
+/// Bounded store of rejected signals, newest last
+pub struct DeadLetterQueue {
+    letters: Mutex<VecDeque<DeadLetter>>,
+    capacity: usize,
+    pushed_tx: broadcast::Sender<DeadLetter>,
+}
+
+impl Default for DeadLetterQueue {
+    fn default() -> Self {
+        Self::new(DEAD_LETTER_CAPACITY)
+    }
+}
+
+impl DeadLetterQueue {
+    pub fn new(capacity: usize) -> Self {
+        let (pushed_tx, _) = broadcast::channel(256);
+        Self {
+            letters: Mutex::new(VecDeque::new()),
+            capacity: capacity.max(1),
+            pushed_tx,
+        }
+    }
+
+    pub fn push(&self, neuron_id: &str, reason: impl Into<String>, signal: NeuronSignal) {
+        let letter = DeadLetter {
+            neuron_id: neuron_id.to_string(),
+            reason: reason.into(),
+            timestamp: Utc::now(),
+            signal,
+        };
+        let _ = self.pushed_tx.send(letter.clone());
+
+        let mut letters = self.letters.lock();
+        if letters.len() >= self.capacity {
+            letters.pop_front();
+        }
+        letters.push_back(letter);
+    }
+
+    /// Subscribe to signals as they are dead-lettered
+    pub fn subscribe(&self) -> broadcast::Receiver<DeadLetter> {
+        self.pushed_tx.subscribe()
+    }
+
+    pub fn list(&self) -> Vec<DeadLetter> {
+        self.letters.lock().iter().cloned().collect()
+    }
+
+    /// Remove the letter of signal `signal_id`, e.g. to retry it
+    pub fn take(&self, signal_id: &str) -> Option<DeadLetter> {
+        let mut letters = self.letters.lock();
+        let index = letters.iter().position(|letter| letter.signal.signal_id.to_string() == signal_id)?;
+        letters.remove(index)
+    }
+
+    /// Put back a letter taken by [`take`](Self::take), in its place by
+    /// time, without announcing it to subscribers again
+    pub fn restore(&self, letter: DeadLetter) {
+        let mut letters = self.letters.lock();
+        let index = letters.partition_point(|kept| kept.timestamp <= letter.timestamp);
+        letters.insert(index, letter);
+        if letters.len() > self.capacity {
+            letters.pop_front();
+        }
+    }
+
+    /// Drop letters older than `cutoff`, returning how many were dropped
+    pub fn drop_before(&self, cutoff: DateTime<Utc>) -> usize {
+        let mut letters = self.letters.lock();
+        let before = letters.len();
+        letters.retain(|letter| letter.timestamp >= cutoff);
+        before - letters.len()
+    }
+
+    pub fn len(&self) -> usize {
+        self.letters.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
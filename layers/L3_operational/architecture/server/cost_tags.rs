@@ -0,0 +1,335 @@
+//! Cost attribution tags
+//!
+//! A chain may be submitted with tags such as `project=checkout`. They are
+//! stored in the root signal's `cost.tags` metadata, which every child
+//! signal inherits, so each step of the chain carries them. When a chain
+//! finishes, the spend of each step is recorded by
+//! [`CostTracker`](crate::cost_tracker::CostTracker) against the step's tag
+//! combination, in daily UTC buckets kept for [`RETENTION_DAYS`]. Reports
+//! group those buckets by the value of one key.
+//!
+//! Keys must be listed in `claude.cost_controls.tags.allowed_keys`, and each
+//! key takes at most `max_values_per_key` distinct values for the life of
+//! the server, so tagging every chain differently cannot grow the ledger
+//! without bound. Spend is kept in memory and, like the hourly and daily
+//! windows, starts over when the server restarts.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::Arc;
+
+use chrono::{DateTime, Duration, FixedOffset, NaiveDate, Utc};
+use serde::Serialize;
+use tokio::sync::broadcast;
+use tracing::error;
+
+use hal9_core::{
+    config::{BudgetPeriod, CostTagConfig},
+    metadata_schema::keys,
+    Error, Result,
+};
+
+use crate::{
+    budget_period::{next_reset, period_start},
+    chain_compare::step_cost,
+    chain_tracker::{ChainRecord, ChainResult, ChainTracker},
+    cost_tracker::CostTracker,
+};
+
+/// Metadata key a chain's tags are carried under
+pub const TAGS_KEY: &str = keys::cost::TAGS;
+
+/// Longest tag key or value accepted
+pub const MAX_TAG_LEN: usize = 64;
+
+/// Days of tagged spend kept, enough for the current and previous month
+pub const RETENTION_DAYS: i64 = 62;
+
+/// Tags of a chain, by key
+pub type CostTags = BTreeMap<String, String>;
+
+/// Keys and values are ASCII letters, digits and `-_.:/`
+fn check_part(kind: &str, part: &str) -> Result<()> {
+    let valid = !part.is_empty()
+        && part.len() <= MAX_TAG_LEN
+        && part.bytes().all(|b| b.is_ascii_alphanumeric() || b"-_.:/".contains(&b));
+    if valid {
+        Ok(())
+    } else {
+        Err(Error::InvalidInput(format!(
+            "Invalid cost tag {} '{}': expected 1 to {} letters, digits or -_.:/",
+            kind, part, MAX_TAG_LEN
+        )))
+    }
+}
+
+/// Check that every key and value of `tags` can be encoded
+pub fn check(tags: &CostTags) -> Result<()> {
+    for (key, value) in tags {
+        check_part("key", key)?;
+        check_part("value", value)?;
+    }
+    Ok(())
+}
+
+/// Metadata value of `tags`: `key=value` pairs, sorted by key and separated
+/// by commas. The same tags always encode to the same string.
+pub fn encode(tags: &CostTags) -> String {
+    tags.iter()
+        .map(|(key, value)| format!("{}={}", key, value))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Parse a metadata value written by [`encode`]
+pub fn decode(value: &str) -> Result<CostTags> {
+    let mut tags = CostTags::new();
+    for pair in value.split(',').filter(|pair| !pair.is_empty()) {
+        let (key, value) = pair.split_once('=').ok_or_else(|| {
+            Error::InvalidInput(format!("Malformed cost tag '{}': expected key=value", pair))
+        })?;
+        check_part("key", key)?;
+        check_part("value", value)?;
+        if tags.insert(key.to_string(), value.to_string()).is_some() {
+            return Err(Error::InvalidInput(format!("Cost tag '{}' given more than once", key)));
+        }
+    }
+    Ok(tags)
+}
+
+/// Chains, signals, tokens and cost attributed to some tags
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct TagSpend {
+    /// Chains whose root signal had the tags
+    pub chains: u64,
+    /// Processed signals that had the tags
+    pub signals: u64,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub cost_usd: f64,
+}
+
+impl TagSpend {
+    fn add(&mut self, other: &TagSpend) {
+        self.chains += other.chains;
+        self.signals += other.signals;
+        self.prompt_tokens += other.prompt_tokens;
+        self.completion_tokens += other.completion_tokens;
+        self.cost_usd += other.cost_usd;
+    }
+}
+
+/// Spend of one tag combination
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TagCombination {
+    pub tags: CostTags,
+    #[serde(flatten)]
+    pub spend: TagSpend,
+}
+
+/// Spend of every combination sharing one value of the report's key
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TagGroup {
+    /// `None` for spend without the key
+    pub value: Option<String>,
+    #[serde(flatten)]
+    pub spend: TagSpend,
+    /// Highest cost first
+    pub combinations: Vec<TagCombination>,
+}
+
+/// Tagged spend over one period, grouped by the value of one key
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TagSpendReport {
+    pub key: String,
+    pub period: BudgetPeriod,
+    pub period_start: DateTime<Utc>,
+    pub period_end: DateTime<Utc>,
+    pub total: TagSpend,
+    /// Highest cost first, with spend lacking the key last
+    pub groups: Vec<TagGroup>,
+}
+
+/// Registered tag values and spend per tag combination
+#[derive(Debug, Default)]
+pub struct TagLedger {
+    values: HashMap<String, HashSet<String>>,
+    /// By UTC day and encoded tag combination
+    spend: BTreeMap<(NaiveDate, String), TagSpend>,
+}
+
+impl TagLedger {
+    /// Check `tags` against the allowed keys and register their values.
+    /// Nothing is registered unless every tag is accepted.
+    pub fn admit(&mut self, tags: &CostTags, config: &CostTagConfig) -> Result<()> {
+        for (key, value) in tags {
+            if !config.allowed_keys.contains(key) {
+                return Err(Error::InvalidInput(format!(
+                    "Cost tag key '{}' is not allowed; allowed keys: {}",
+                    key,
+                    config.allowed_keys.join(", ")
+                )));
+            }
+            let known = self.values.get(key);
+            let count = known.map_or(0, HashSet::len);
+            if !known.is_some_and(|values| values.contains(value)) && count >= config.max_values_per_key {
+                return Err(Error::InvalidInput(format!(
+                    "Cost tag '{}' already has {} distinct values; '{}' refused",
+                    key, count, value
+                )));
+            }
+        }
+        for (key, value) in tags {
+            self.values.entry(key.clone()).or_default().insert(value.clone());
+        }
+        Ok(())
+    }
+
+    /// Distinct values registered for `key`
+    pub fn value_count(&self, key: &str) -> usize {
+        self.values.get(key).map_or(0, HashSet::len)
+    }
+
+    /// Add `spend` to `tags` on the day of `at`, dropping days past
+    /// [`RETENTION_DAYS`]
+    pub fn record(&mut self, tags: &CostTags, spend: TagSpend, at: DateTime<Utc>) {
+        let day = at.date_naive();
+        self.spend.entry((day, encode(tags))).or_default().add(&spend);
+
+        let oldest = (day - Duration::days(RETENTION_DAYS), String::new());
+        if self.spend.first_key_value().is_some_and(|(first, _)| *first < oldest) {
+            self.spend = self.spend.split_off(&oldest);
+        }
+    }
+
+    /// Spend of the `period` containing `now` of every combination that
+    /// carries all of `tags`
+    pub fn spend_with(&self, tags: &CostTags, period: BudgetPeriod, now: DateTime<Utc>) -> TagSpend {
+        let (start, end) = period_bounds(period, now);
+        let days = (start.date_naive(), String::new())..(end.date_naive(), String::new());
+        let mut total = TagSpend::default();
+        for ((_, encoded), spend) in self.spend.range(days) {
+            let combination = decode(encoded).unwrap_or_default();
+            if tags.iter().all(|(key, value)| combination.get(key) == Some(value)) {
+                total.add(spend);
+            }
+        }
+        total
+    }
+
+    /// Spend of the `period` containing `now`, grouped by the value of `key`
+    pub fn report(&self, key: &str, period: BudgetPeriod, now: DateTime<Utc>) -> TagSpendReport {
+        let (start, end) = period_bounds(period, now);
+
+        let mut combinations: BTreeMap<&str, TagSpend> = BTreeMap::new();
+        let days = (start.date_naive(), String::new())..(end.date_naive(), String::new());
+        for ((_, tags), spend) in self.spend.range(days) {
+            combinations.entry(tags.as_str()).or_default().add(spend);
+        }
+
+        let mut total = TagSpend::default();
+        let mut groups: BTreeMap<Option<String>, TagGroup> = BTreeMap::new();
+        for (encoded, spend) in combinations {
+            // Recorded tags were accepted, so they decode
+            let tags = decode(encoded).unwrap_or_default();
+            let value = tags.get(key).cloned();
+            let group = groups.entry(value.clone()).or_insert_with(|| TagGroup {
+                value,
+                spend: TagSpend::default(),
+                combinations: Vec::new(),
+            });
+            group.spend.add(&spend);
+            group.combinations.push(TagCombination { tags, spend });
+            total.add(&spend);
+        }
+
+        let by_cost = |a: &TagSpend, b: &TagSpend| b.cost_usd.total_cmp(&a.cost_usd);
+        let mut groups: Vec<TagGroup> = groups.into_values().collect();
+        for group in &mut groups {
+            group.combinations.sort_by(|a, b| by_cost(&a.spend, &b.spend));
+        }
+        groups.sort_by(|a, b| a.value.is_none().cmp(&b.value.is_none()).then_with(|| by_cost(&a.spend, &b.spend)));
+
+        TagSpendReport {
+            key: key.to_string(),
+            period,
+            period_start: start,
+            period_end: end,
+            total,
+            groups,
+        }
+    }
+}
+
+/// Start and end of the UTC `period` containing `now`
+fn period_bounds(period: BudgetPeriod, now: DateTime<Utc>) -> (DateTime<Utc>, DateTime<Utc>) {
+    let utc = FixedOffset::east_opt(0).unwrap();
+    let start = period_start(period, utc, now);
+    (start, next_reset(period, utc, start))
+}
+
+/// Spend of each tag combination in a finished chain. The chain is counted
+/// against its root's tags; steps against their own, which differ only if a
+/// signal lost its parent's metadata on the way.
+pub fn chain_spend(record: &ChainRecord, default_model: &str) -> Vec<(CostTags, TagSpend)> {
+    let mut spend: BTreeMap<&CostTags, TagSpend> = BTreeMap::new();
+    spend.entry(&record.tags).or_default().chains = 1;
+    for step in &record.steps {
+        let entry = spend.entry(&step.tags).or_default();
+        entry.signals += 1;
+        entry.prompt_tokens += step.prompt_tokens;
+        entry.completion_tokens += step.completion_tokens;
+        entry.cost_usd += step_cost(step, default_model);
+    }
+    spend.into_iter().map(|(tags, spend)| (tags.clone(), spend)).collect()
+}
+
+/// CSV export of a report, one row per tag combination
+pub fn to_csv(report: &TagSpendReport) -> String {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    let _ = writer.write_record(["value", "tags", "chains", "signals", "prompt_tokens", "completion_tokens", "cost_usd"]);
+    for group in &report.groups {
+        for combination in &group.combinations {
+            let spend = &combination.spend;
+            let _ = writer.write_record([
+                group.value.clone().unwrap_or_default(),
+                encode(&combination.tags),
+                spend.chains.to_string(),
+                spend.signals.to_string(),
+                spend.prompt_tokens.to_string(),
+                spend.completion_tokens.to_string(),
+                spend.cost_usd.to_string(),
+            ]);
+        }
+    }
+    String::from_utf8(writer.into_inner().unwrap_or_default()).unwrap_or_default()
+}
+
+/// Record the spend of every finished chain with `cost_tracker`, by tag
+/// combination and by layer, pricing steps without a known model as
+/// `default_model`
+pub async fn recorder_task(
+    cost_tracker: Arc<CostTracker>,
+    chain_tracker: Arc<ChainTracker>,
+    mut finished: broadcast::Receiver<ChainResult>,
+    default_model: String,
+) {
+    loop {
+        match finished.recv().await {
+            Ok(result) => {
+                if let Some(record) = chain_tracker.get(&result.chain_id) {
+                    for (tags, spend) in chain_spend(&record, &default_model) {
+                        cost_tracker.record_tag_spend(&tags, spend, record.created_at);
+                    }
+                    for step in &record.steps {
+                        let tokens = step.prompt_tokens + step.completion_tokens;
+                        cost_tracker.record_layer_spend(&step.layer, tokens, step_cost(step, &default_model));
+                    }
+                }
+            }
+            Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                error!("Cost tag recorder lagged, {} chains not recorded", skipped);
+            }
+            Err(broadcast::error::RecvError::Closed) => break,
+        }
+    }
+}
@@ -1,12 +1,18 @@
 //! Cost tracking and control for Claude API usage
 
+use std::collections::BTreeMap;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
+use chrono::{DateTime, Utc};
 use tokio::sync::RwLock;
-use tracing::{info, warn};
-use hal9_core::{Result, Error, config::CostControls};
+use tracing::{error, info, warn};
+use hal9_core::{Result, Error, config::{BudgetPeriod, CostControls}};
+use crate::budget_period::{PeriodBudget, PeriodStatus};
+use crate::cost_tags::{CostTags, TagLedger, TagSpend, TagSpendReport};
 use crate::metrics::Metrics;
 
+pub use hal9_api_types::{CostStats, LayerCost};
+
 /// Time-based cost window
 #[derive(Debug, Clone)]
 struct CostWindow {
@@ -40,13 +46,19 @@ impl CostWindow {
 /// Cost tracker for monitoring and controlling API costs
 pub struct CostTracker {
     /// Cost control configuration
-    config: CostControls,
+    config: parking_lot::RwLock<CostControls>,
     /// Hourly cost window
     hourly_window: Arc<RwLock<CostWindow>>,
     /// Daily cost window
     daily_window: Arc<RwLock<CostWindow>>,
     /// Total costs since start
     total_cost: Arc<RwLock<f64>>,
+    /// Recurring budget period, if configured
+    period: Option<Arc<RwLock<PeriodBudget>>>,
+    /// Spend per cost attribution tag combination
+    tag_ledger: parking_lot::Mutex<TagLedger>,
+    /// Spend per layer since start
+    layer_spend: parking_lot::Mutex<BTreeMap<String, LayerCost>>,
     /// Alert callback
     alert_callback: Option<Arc<dyn Fn(String) + Send + Sync>>,
     /// Metrics integration
@@ -56,11 +68,24 @@ pub struct CostTracker {
 impl CostTracker {
     /// Create a new cost tracker
     pub fn new(config: CostControls) -> Self {
+        let period = config.budget_period.clone().and_then(|period_config| {
+            match PeriodBudget::load(period_config, Utc::now()) {
+                Ok(period) => Some(Arc::new(RwLock::new(period))),
+                Err(e) => {
+                    error!("Budget period disabled: {}", e);
+                    None
+                }
+            }
+        });
+        
         Self {
-            config,
+            config: parking_lot::RwLock::new(config),
             hourly_window: Arc::new(RwLock::new(CostWindow::new())),
             daily_window: Arc::new(RwLock::new(CostWindow::new())),
             total_cost: Arc::new(RwLock::new(0.0)),
+            period,
+            tag_ledger: parking_lot::Mutex::new(TagLedger::default()),
+            layer_spend: parking_lot::Mutex::new(BTreeMap::new()),
             alert_callback: None,
             metrics: None,
         }
@@ -79,14 +104,25 @@ impl CostTracker {
         self.alert_callback = Some(Arc::new(callback));
     }
     
+    /// Apply changed limits and alert threshold from now on. Spending so
+    /// far counts against the new limits; the budget period is kept.
+    pub fn set_limits(&self, controls: &CostControls) {
+        let mut config = self.config.write();
+        config.max_cost_per_hour = controls.max_cost_per_hour;
+        config.max_cost_per_day = controls.max_cost_per_day;
+        config.max_tokens_per_request = controls.max_tokens_per_request;
+        config.alert_threshold = controls.alert_threshold;
+    }
+    
     /// Check if a request with given tokens is allowed
     pub async fn check_request(&self, estimated_tokens: u32) -> Result<()> {
+        let config = self.config.read().clone();
         // Check token limit
-        if estimated_tokens > self.config.max_tokens_per_request {
+        if estimated_tokens > config.max_tokens_per_request {
             return Err(Error::CostLimit {
                 reason: format!(
                     "Request tokens {} exceeds limit {}",
-                    estimated_tokens, self.config.max_tokens_per_request
+                    estimated_tokens, config.max_tokens_per_request
                 ),
             });
         }
@@ -96,32 +132,74 @@ impl CostTracker {
         
         // Check hourly limit (rough estimate)
         let hourly_cost = self.hourly_window.read().await.cost;
-        if hourly_cost >= self.config.max_cost_per_hour {
+        if hourly_cost >= config.max_cost_per_hour {
             return Err(Error::CostLimit {
                 reason: format!(
                     "Hourly cost ${:.2} exceeds limit ${:.2}",
-                    hourly_cost, self.config.max_cost_per_hour
+                    hourly_cost, config.max_cost_per_hour
                 ),
             });
         }
         
         // Check daily limit
         let daily_cost = self.daily_window.read().await.cost;
-        if daily_cost >= self.config.max_cost_per_day {
+        if daily_cost >= config.max_cost_per_day {
             return Err(Error::CostLimit {
                 reason: format!(
                     "Daily cost ${:.2} exceeds limit ${:.2}",
-                    daily_cost, self.config.max_cost_per_day
+                    daily_cost, config.max_cost_per_day
                 ),
             });
         }
         
+        // Check the period budget; with a fallback model configured the
+        // request goes ahead on the cheaper model instead
+        if let Some(period) = &self.period {
+            let mut period = period.write().await;
+            self.advance_period(&mut period);
+            if period.is_exhausted() && period.config().fallback_model.is_none() {
+                let status = period.status();
+                return Err(Error::CostLimit {
+                    reason: format!(
+                        "Budget of ${:.2} for this period exhausted until {}",
+                        period.available(), status.resets_at
+                    ),
+                });
+            }
+        }
+        
         // Check if we're approaching limits
         self.check_alerts(hourly_cost, daily_cost).await;
         
         Ok(())
     }
     
+    /// Model to use instead of the configured one, when the period budget
+    /// is exhausted and grace mode is enabled
+    pub async fn downgrade_model(&self) -> Option<String> {
+        let period = self.period.as_ref()?;
+        let mut period = period.write().await;
+        self.advance_period(&mut period);
+        period.downgrade_model().map(str::to_string)
+    }
+    
+    /// Current budget period, if one is configured
+    pub async fn period_status(&self) -> Option<PeriodStatus> {
+        let period = self.period.as_ref()?;
+        let mut period = period.write().await;
+        self.advance_period(&mut period);
+        Some(period.status())
+    }
+    
+    /// Roll the period forward, persisting any reset
+    fn advance_period(&self, period: &mut PeriodBudget) {
+        if period.advance(Utc::now()) {
+            if let Err(e) = period.save() {
+                warn!("Failed to persist budget period: {}", e);
+            }
+        }
+    }
+    
     /// Record actual cost after request
     pub async fn record_cost(&self, cost: f64, tokens: u64) {
         // Update windows
@@ -132,6 +210,15 @@ impl CostTracker {
         self.daily_window.write().await.add_cost(cost, tokens);
         *self.total_cost.write().await += cost;
         
+        if let Some(period) = &self.period {
+            let mut period = period.write().await;
+            self.advance_period(&mut period);
+            period.record(cost);
+            if let Err(e) = period.save() {
+                warn!("Failed to persist budget period: {}", e);
+            }
+        }
+        
         // Log cost
         info!(
             "API cost recorded: ${:.4} for {} tokens (hourly: ${:.2}, daily: ${:.2})",
@@ -154,6 +241,44 @@ impl CostTracker {
         self.check_alerts(hourly_cost, daily_cost).await;
     }
     
+    /// Check cost attribution tags against the allowed keys and register
+    /// their values, refusing values past a key's distinct-value limit
+    pub fn admit_tags(&self, tags: &CostTags) -> Result<()> {
+        let config = self.config.read().tags.clone();
+        self.tag_ledger.lock().admit(tags, &config)
+    }
+    
+    /// Spend so far in the current UTC `period` of chains carrying all of
+    /// `tags`
+    pub fn spend_with_tags(&self, tags: &CostTags, period: BudgetPeriod) -> TagSpend {
+        self.tag_ledger.lock().spend_with(tags, period, Utc::now())
+    }
+
+    /// Record spend against a tag combination, on the day of `at`
+    pub fn record_tag_spend(&self, tags: &CostTags, spend: TagSpend, at: DateTime<Utc>) {
+        self.tag_ledger.lock().record(tags, spend, at);
+    }
+    
+    /// Record the spend of one signal processed on `layer`
+    pub fn record_layer_spend(&self, layer: &str, tokens: u64, cost: f64) {
+        let mut spend = self.layer_spend.lock();
+        let entry = spend.entry(layer.to_string()).or_insert_with(|| LayerCost {
+            layer: layer.to_string(),
+            ..Default::default()
+        });
+        entry.signals += 1;
+        entry.tokens += tokens;
+        entry.cost += cost;
+    }
+    
+    /// Tagged spend of the current `period`, grouped by the value of `key`
+    pub fn spend_by_tag(&self, key: &str, period: BudgetPeriod) -> Result<TagSpendReport> {
+        if !self.config.read().tags.allowed_keys.iter().any(|allowed| allowed == key) {
+            return Err(Error::InvalidInput(format!("Unknown cost tag key '{}'", key)));
+        }
+        Ok(self.tag_ledger.lock().report(key, period, Utc::now()))
+    }
+    
     /// Update windows if they've expired
     async fn update_windows(&self) {
         // Check hourly window
@@ -174,15 +299,16 @@ impl CostTracker {
     
     /// Check if we should send alerts
     async fn check_alerts(&self, hourly_cost: f64, daily_cost: f64) {
-        let hourly_ratio = hourly_cost / self.config.max_cost_per_hour;
-        let daily_ratio = daily_cost / self.config.max_cost_per_day;
+        let config = self.config.read().clone();
+        let hourly_ratio = hourly_cost / config.max_cost_per_hour;
+        let daily_ratio = daily_cost / config.max_cost_per_day;
         
-        if hourly_ratio >= self.config.alert_threshold {
+        if hourly_ratio >= config.alert_threshold {
             let msg = format!(
                 "⚠️ Hourly cost alert: ${:.2} ({:.0}% of ${:.2} limit)",
                 hourly_cost,
                 hourly_ratio * 100.0,
-                self.config.max_cost_per_hour
+                config.max_cost_per_hour
             );
             warn!("{}", msg);
             
@@ -191,12 +317,12 @@ impl CostTracker {
             }
         }
         
-        if daily_ratio >= self.config.alert_threshold {
+        if daily_ratio >= config.alert_threshold {
             let msg = format!(
                 "⚠️ Daily cost alert: ${:.2} ({:.0}% of ${:.2} limit)",
                 daily_cost,
                 daily_ratio * 100.0,
-                self.config.max_cost_per_day
+                config.max_cost_per_day
             );
             warn!("{}", msg);
             
@@ -206,32 +332,38 @@ impl CostTracker {
         }
     }
     
+    /// Largest fraction of the hourly, daily or period budget spent so far
+    pub async fn budget_utilization(&self) -> f64 {
+        self.update_windows().await;
+        let config = self.config.read().clone();
+        let hourly = self.hourly_window.read().await.cost / config.max_cost_per_hour;
+        let daily = self.daily_window.read().await.cost / config.max_cost_per_day;
+        let period = match self.period_status().await {
+            Some(status) if status.budget + status.carried_over > 0.0 => {
+                status.consumed / (status.budget + status.carried_over)
+            }
+            _ => 0.0,
+        };
+        hourly.max(daily).max(period)
+    }
+    
     /// Get current cost statistics
     pub async fn get_stats(&self) -> CostStats {
+        let config = self.config.read().clone();
         CostStats {
             hourly_cost: self.hourly_window.read().await.cost,
             hourly_tokens: self.hourly_window.read().await.tokens,
             daily_cost: self.daily_window.read().await.cost,
             daily_tokens: self.daily_window.read().await.tokens,
             total_cost: *self.total_cost.read().await,
-            hourly_limit: self.config.max_cost_per_hour,
-            daily_limit: self.config.max_cost_per_day,
+            hourly_limit: config.max_cost_per_hour,
+            daily_limit: config.max_cost_per_day,
+            period: self.period_status().await,
+            by_layer: self.layer_spend.lock().values().cloned().collect(),
         }
     }
 }
 
-/// Cost statistics
-#[derive(Debug, Clone, serde::Serialize)]
-pub struct CostStats {
-    pub hourly_cost: f64,
-    pub hourly_tokens: u64,
-    pub daily_cost: f64,
-    pub daily_tokens: u64,
-    pub total_cost: f64,
-    pub hourly_limit: f64,
-    pub daily_limit: f64,
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -243,6 +375,8 @@ mod tests {
             max_cost_per_day: 10.0,
             max_tokens_per_request: 1000,
             alert_threshold: 0.8,
+            budget_period: None,
+            tags: Default::default(),
         };
         
         let tracker = CostTracker::new(config);
@@ -261,5 +395,22 @@ mod tests {
         
         // Should now reject
         assert!(tracker.check_request(100).await.is_err());
+        
+        // Raising the limit at runtime lets requests through again
+        let mut raised = CostControls {
+            max_cost_per_hour: 5.0,
+            max_cost_per_day: 10.0,
+            max_tokens_per_request: 1000,
+            alert_threshold: 0.8,
+            budget_period: None,
+            tags: Default::default(),
+        };
+        tracker.set_limits(&raised);
+        assert!(tracker.check_request(100).await.is_ok());
+        assert_eq!(tracker.get_stats().await.hourly_limit, 5.0);
+        
+        raised.max_tokens_per_request = 50;
+        tracker.set_limits(&raised);
+        assert!(tracker.check_request(100).await.is_err());
     }
 }
\ No newline at end of file
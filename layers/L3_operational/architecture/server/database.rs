@@ -7,6 +7,7 @@ use sqlx::sqlite::{SqlitePool, SqlitePoolOptions};
 use std::time::Duration;
 use std::str::FromStr;
 use tracing::info;
+use hal9_core::sqlite::{self, SqliteTuning};
 
 /// Database configuration
 #[derive(Debug, Clone)]
@@ -31,6 +32,25 @@ pub struct DatabaseConfig {
     
     /// Maximum lifetime of a connection
     pub max_lifetime: Duration,
+    
+    /// WAL, busy timeout and write retry settings for SQLite
+    pub sqlite: SqliteTuning,
+}
+
+impl DatabaseConfig {
+    /// Default pool settings for a `sqlite:` or `postgres://` URL
+    pub fn for_url(url: &str) -> Self {
+        let database_type = if url.starts_with("postgres") {
+            DatabaseType::Postgres
+        } else {
+            DatabaseType::Sqlite
+        };
+        Self {
+            database_type,
+            url: url.to_string(),
+            ..Default::default()
+        }
+    }
 }
 
 impl Default for DatabaseConfig {
@@ -43,6 +63,7 @@ impl Default for DatabaseConfig {
             connection_timeout: Duration::from_secs(30),
             idle_timeout: Duration::from_secs(600),
             max_lifetime: Duration::from_secs(1800),
+            sqlite: SqliteTuning::default(),
         }
     }
 }
@@ -72,6 +93,18 @@ pub enum DatabasePool {
     Postgres(PgPool),
 }
 
+/// Run `$body` with `$pool` bound to whichever pool `$database` holds, for
+/// SQL both backends take as is
+macro_rules! on_pool {
+    ($database:expr, |$pool:ident| $body:expr) => {
+        match $database {
+            $crate::database::DatabasePool::Sqlite($pool) => $body,
+            $crate::database::DatabasePool::Postgres($pool) => $body,
+        }
+    };
+}
+pub(crate) use on_pool;
+
 impl DatabasePool {
     /// Create a new database pool
     pub async fn new(config: &DatabaseConfig) -> Result<Self> {
@@ -79,15 +112,19 @@ impl DatabasePool {
             DatabaseType::Sqlite => {
                 info!("Connecting to SQLite database: {}", config.url);
                 
+                // Connections beyond the readers SQLite can serve only queue on its lock
                 let pool = SqlitePoolOptions::new()
-                    .max_connections(config.max_connections)
-                    .min_connections(config.min_connections)
+                    .max_connections(config.max_connections.min(config.sqlite.max_readers + 1))
+                    .min_connections(config.min_connections.min(config.sqlite.max_readers + 1))
                     .acquire_timeout(config.connection_timeout)
                     .idle_timeout(Some(config.idle_timeout))
                     .max_lifetime(Some(config.max_lifetime))
-                    .connect(&config.url)
+                    .connect_with(config.sqlite.connect_options(&config.url)?)
                     .await?;
                 
+                let rows: Vec<String> = sqlx::query_scalar(sqlite::QUICK_CHECK).fetch_all(&pool).await?;
+                sqlite::check_integrity(&config.url, rows)?;
+                
                 Ok(Self::Sqlite(pool))
             }
             DatabaseType::Postgres => {
@@ -153,6 +190,7 @@ impl DatabasePool {
                 connection_timeout: Duration::from_secs(30),
                 idle_timeout: Duration::from_secs(600),
                 max_lifetime: Duration::from_secs(1800),
+                sqlite: SqliteTuning::default(),
             },
             Self::Postgres(_) => DatabaseConfig {
                 database_type: DatabaseType::Postgres,
@@ -162,6 +200,7 @@ impl DatabasePool {
                 connection_timeout: Duration::from_secs(30),
                 idle_timeout: Duration::from_secs(600),
                 max_lifetime: Duration::from_secs(1800),
+                sqlite: SqliteTuning::default(),
             },
         };
         
@@ -178,23 +217,9 @@ impl DatabasePool {
         Ok(pool)
     }
     
-    /// Run migrations
-    pub async fn migrate(&self) -> Result<()> {
-        match self {
-            Self::Sqlite(pool) => {
-                info!("Running SQLite migrations");
-                sqlx::migrate!("./migrations/sqlite")
-                    .run(pool)
-                    .await?;
-            }
-            Self::Postgres(pool) => {
-                info!("Running PostgreSQL migrations");
-                sqlx::migrate!("./migrations/postgres")
-                    .run(pool)
-                    .await?;
-            }
-        }
-        Ok(())
+    /// Apply pending migrations; see [`crate::database_migrations::migrate`]
+    pub async fn migrate(&self, lock_timeout: Duration) -> Result<crate::database_migrations::MigrationReport> {
+        Ok(crate::database_migrations::migrate(self, lock_timeout).await?)
     }
     
     /// Get pool metrics
@@ -0,0 +1,406 @@
+//! Schema migrations of the server database
+//!
+//! Migrations are the numbered SQL files under `migrations/sqlite` and
+//! `migrations/postgres`, compiled into the binary. [`migrate`] applies the
+//! pending ones in order, each in its own transaction, while holding an
+//! advisory lock: `pg_advisory_lock` on PostgreSQL, a lock file next to a
+//! SQLite database. Replicas starting together therefore apply each
+//! migration once, the others waiting and then finding nothing to do.
+//!
+//! A database migrated by a newer release is refused rather than used:
+//! this binary cannot know what the newer schema changed, and migrations
+//! are never reverted automatically.
+
+use std::collections::HashSet;
+use std::fs::{File, OpenOptions};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use fs2::FileExt;
+use serde::{Deserialize, Serialize};
+use sqlx::migrate::{MigrateError, Migrator};
+use sqlx::pool::PoolConnection;
+use sqlx::sqlite::SqlitePool;
+use sqlx::Postgres;
+use thiserror::Error;
+use tracing::{info, warn};
+
+use hal9_core::config::DatabaseConfig;
+use hal9_core::Error;
+
+use crate::database::{self, DatabasePool, DatabaseType};
+
+/// Migrations of SQLite databases
+pub static SQLITE_MIGRATIONS: Migrator = sqlx::migrate!("./migrations/sqlite");
+
+/// Migrations of PostgreSQL databases
+pub static POSTGRES_MIGRATIONS: Migrator = sqlx::migrate!("./migrations/postgres");
+
+/// Table applied migrations are recorded in
+pub const MIGRATIONS_TABLE: &str = "_sqlx_migrations";
+
+/// Advisory lock held while migrating a PostgreSQL database ("HAL9MIGR")
+const POSTGRES_LOCK_KEY: i64 = 0x4841_4c39_4d49_4752;
+
+/// How often a replica waiting for another's migrations retries the lock
+const LOCK_POLL: Duration = Duration::from_millis(250);
+
+pub fn migrator(database_type: DatabaseType) -> &'static Migrator {
+    match database_type {
+        DatabaseType::Sqlite => &SQLITE_MIGRATIONS,
+        DatabaseType::Postgres => &POSTGRES_MIGRATIONS,
+    }
+}
+
+/// A migration, by version
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MigrationInfo {
+    pub version: i64,
+    pub description: String,
+}
+
+/// How a database's schema compares to this binary's migrations
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SchemaState {
+    /// Every migration is applied
+    Current,
+    /// Migrations are pending
+    Behind,
+    /// Migrated by a newer release
+    Ahead,
+    /// A migration failed partway
+    Dirty,
+}
+
+/// Schema version of a database against the version this binary expects
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SchemaStatus {
+    /// "sqlite" or "postgres"
+    pub database: String,
+    pub state: SchemaState,
+    /// Newest applied migration; none on a fresh database
+    pub current: Option<i64>,
+    /// Newest migration this binary carries
+    pub expected: i64,
+    pub pending: Vec<MigrationInfo>,
+    /// Applied migrations this binary does not carry
+    pub unknown: Vec<i64>,
+    /// Migration that failed partway
+    pub dirty: Option<i64>,
+}
+
+impl SchemaStatus {
+    /// Compare the migrations recorded as applied, with whether each
+    /// succeeded, to the ones this binary carries
+    fn new(database_type: DatabaseType, applied: &[(i64, bool)]) -> Self {
+        let known: Vec<_> = migrator(database_type)
+            .iter()
+            .filter(|m| !m.migration_type.is_down_migration())
+            .collect();
+        let versions: HashSet<i64> = known.iter().map(|m| m.version).collect();
+        let succeeded: HashSet<i64> = applied.iter().filter(|(_, success)| *success).map(|(v, _)| *v).collect();
+
+        let dirty = applied.iter().find(|(_, success)| !success).map(|(version, _)| *version);
+        let unknown: Vec<i64> = applied.iter().map(|(v, _)| *v).filter(|v| !versions.contains(v)).collect();
+        let pending: Vec<MigrationInfo> = known
+            .iter()
+            .filter(|m| !succeeded.contains(&m.version))
+            .map(|m| MigrationInfo { version: m.version, description: m.description.to_string() })
+            .collect();
+
+        let state = if dirty.is_some() {
+            SchemaState::Dirty
+        } else if !unknown.is_empty() {
+            SchemaState::Ahead
+        } else if !pending.is_empty() {
+            SchemaState::Behind
+        } else {
+            SchemaState::Current
+        };
+        Self {
+            database: match database_type {
+                DatabaseType::Sqlite => "sqlite",
+                DatabaseType::Postgres => "postgres",
+            }
+            .to_string(),
+            state,
+            current: succeeded.iter().max().copied(),
+            expected: known.last().map_or(0, |m| m.version),
+            pending,
+            unknown,
+            dirty,
+        }
+    }
+
+    /// Fail unless the database has exactly this binary's schema
+    pub fn check(&self) -> Result<(), SchemaError> {
+        match self.state {
+            SchemaState::Current => Ok(()),
+            SchemaState::Behind => Err(SchemaError::Behind { current: self.current, expected: self.expected }),
+            SchemaState::Ahead => Err(SchemaError::TooNew {
+                current: self.unknown.iter().chain(self.current.as_ref()).max().copied().unwrap_or_default(),
+                expected: self.expected,
+            }),
+            SchemaState::Dirty => Err(SchemaError::Dirty(self.dirty.unwrap_or_default())),
+        }
+    }
+}
+
+/// Migrations applied by one [`migrate`] call
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MigrationReport {
+    pub from: Option<i64>,
+    pub to: Option<i64>,
+    pub applied: Vec<MigrationInfo>,
+    pub duration_ms: u64,
+}
+
+#[derive(Debug, Error)]
+pub enum SchemaError {
+    #[error(
+        "Database schema is at version {current} but this binary only knows migrations up to {expected}: \
+         it was migrated by a newer release. Downgrading is not supported; run that release or a newer one, \
+         or restore a backup taken before the upgrade"
+    )]
+    TooNew { current: i64, expected: i64 },
+
+    #[error(
+        "Database schema is at version {} but this binary needs {expected}; run `hal9-server --migrate-only` \
+         or set database.migrate_on_start",
+        current.map_or_else(|| "none".to_string(), |v| v.to_string())
+    )]
+    Behind { current: Option<i64>, expected: i64 },
+
+    #[error("Migration {0} failed partway; repair the database and delete its row from {MIGRATIONS_TABLE} before starting")]
+    Dirty(i64),
+
+    #[error("Migration {0} was changed after it was applied")]
+    Modified(i64),
+
+    #[error("Timed out after {}s waiting for another process to finish migrating", .0.as_secs())]
+    LockTimeout(Duration),
+
+    #[error("Failed to lock {path}: {source}")]
+    Lock { path: String, source: std::io::Error },
+
+    #[error("Migration failed: {0}")]
+    Migrate(MigrateError),
+
+    #[error("Database error: {0}")]
+    Database(#[from] sqlx::Error),
+}
+
+impl SchemaError {
+    fn from_migrate(error: MigrateError, expected: i64) -> Self {
+        match error {
+            MigrateError::VersionMissing(current) => Self::TooNew { current, expected },
+            MigrateError::VersionMismatch(version) => Self::Modified(version),
+            MigrateError::Dirty(version) => Self::Dirty(version),
+            error => Self::Migrate(error),
+        }
+    }
+}
+
+impl From<SchemaError> for Error {
+    fn from(error: SchemaError) -> Self {
+        Error::Migration(error.to_string())
+    }
+}
+
+/// Connect to the configured database; none when there is no `url`
+pub async fn connect(config: &DatabaseConfig) -> hal9_core::Result<Option<DatabasePool>> {
+    let Some(url) = &config.url else {
+        return Ok(None);
+    };
+    DatabasePool::new(&database::DatabaseConfig::for_url(url))
+        .await
+        .map(Some)
+        .map_err(|e| Error::Storage(format!("Failed to connect to the database: {}", e)))
+}
+
+/// Bring a database to this binary's schema at startup: migrate it when
+/// `config.migrate_on_start`, otherwise only check nothing is pending.
+/// Returns the migrations applied.
+pub async fn prepare(pool: &DatabasePool, config: &DatabaseConfig) -> Result<Vec<MigrationInfo>, SchemaError> {
+    if config.migrate_on_start {
+        Ok(migrate(pool, Duration::from_secs(config.lock_timeout_secs)).await?.applied)
+    } else {
+        status(pool).await?.check()?;
+        Ok(Vec::new())
+    }
+}
+
+/// Schema version of the database, read without changing anything
+pub async fn status(pool: &DatabasePool) -> Result<SchemaStatus, SchemaError> {
+    let applied = applied_migrations(pool).await?;
+    Ok(SchemaStatus::new(pool.database_type(), &applied))
+}
+
+/// Migrations recorded as applied, with whether each succeeded
+async fn applied_migrations(pool: &DatabasePool) -> Result<Vec<(i64, bool)>, sqlx::Error> {
+    let query = format!("SELECT version, success FROM {} ORDER BY version", MIGRATIONS_TABLE);
+    match pool {
+        DatabasePool::Sqlite(pool) => {
+            let tables: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = ?1")
+                .bind(MIGRATIONS_TABLE)
+                .fetch_one(pool)
+                .await?;
+            if tables == 0 {
+                return Ok(Vec::new());
+            }
+            sqlx::query_as(&query).fetch_all(pool).await
+        }
+        DatabasePool::Postgres(pool) => {
+            let exists: bool = sqlx::query_scalar(
+                "SELECT EXISTS (SELECT 1 FROM information_schema.tables \
+                 WHERE table_schema = current_schema() AND table_name = $1)",
+            )
+            .bind(MIGRATIONS_TABLE)
+            .fetch_one(pool)
+            .await?;
+            if !exists {
+                return Ok(Vec::new());
+            }
+            sqlx::query_as(&query).fetch_all(pool).await
+        }
+    }
+}
+
+/// Apply pending migrations, waiting up to `lock_timeout` for another
+/// process migrating the same database. Refuses databases migrated by a
+/// newer release or with a failed migration.
+pub async fn migrate(pool: &DatabasePool, lock_timeout: Duration) -> Result<MigrationReport, SchemaError> {
+    let started = Instant::now();
+    let before = match pool {
+        DatabasePool::Sqlite(sqlite) => {
+            let _lock = SqliteLock::acquire(sqlite, lock_timeout).await?;
+            let before = gate(pool).await?;
+            if before.state == SchemaState::Behind {
+                SQLITE_MIGRATIONS.run(sqlite).await.map_err(|e| SchemaError::from_migrate(e, before.expected))?;
+            }
+            before
+        }
+        DatabasePool::Postgres(postgres) => {
+            let mut conn = postgres.acquire().await?;
+            lock_postgres(&mut conn, lock_timeout).await?;
+            let result: Result<SchemaStatus, SchemaError> = async {
+                let before = gate(pool).await?;
+                if before.state == SchemaState::Behind {
+                    POSTGRES_MIGRATIONS
+                        .run(&mut *conn)
+                        .await
+                        .map_err(|e| SchemaError::from_migrate(e, before.expected))?;
+                }
+                Ok(before)
+            }
+            .await;
+            // The lock is the session's, so closing the connection releases it too
+            let unlocked = sqlx::query("SELECT pg_advisory_unlock($1)")
+                .bind(POSTGRES_LOCK_KEY)
+                .execute(&mut *conn)
+                .await;
+            if unlocked.is_err() {
+                let _ = sqlx::Connection::close(conn.detach()).await;
+            }
+            result?
+        }
+    };
+
+    let after = status(pool).await?;
+    if !before.pending.is_empty() {
+        info!(
+            "Migrated {} database from version {} to {} in {} ms",
+            after.database,
+            before.current.map_or_else(|| "none".to_string(), |v| v.to_string()),
+            after.expected,
+            started.elapsed().as_millis()
+        );
+    }
+    Ok(MigrationReport {
+        from: before.current,
+        to: after.current,
+        applied: before.pending,
+        duration_ms: started.elapsed().as_millis() as u64,
+    })
+}
+
+/// Schema status, failing for a database this binary must not migrate
+async fn gate(pool: &DatabasePool) -> Result<SchemaStatus, SchemaError> {
+    let status = status(pool).await?;
+    if matches!(status.state, SchemaState::Ahead | SchemaState::Dirty) {
+        status.check()?;
+    }
+    Ok(status)
+}
+
+async fn lock_postgres(conn: &mut PoolConnection<Postgres>, timeout: Duration) -> Result<(), SchemaError> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        let locked: bool = sqlx::query_scalar("SELECT pg_try_advisory_lock($1)")
+            .bind(POSTGRES_LOCK_KEY)
+            .fetch_one(&mut **conn)
+            .await?;
+        if locked {
+            return Ok(());
+        }
+        if Instant::now() >= deadline {
+            return Err(SchemaError::LockTimeout(timeout));
+        }
+        tokio::time::sleep(LOCK_POLL).await;
+    }
+}
+
+/// Exclusive lock on `<database>.migrate.lock`, released when dropped or
+/// when the process exits
+struct SqliteLock {
+    file: Option<File>,
+}
+
+impl SqliteLock {
+    async fn acquire(pool: &SqlitePool, timeout: Duration) -> Result<Self, SchemaError> {
+        let filename = (*pool.connect_options()).clone().get_filename();
+        let Some(path) = lock_path(&filename) else {
+            // Nothing else can open an in-memory database
+            return Ok(Self { file: None });
+        };
+        let lock_error = |source| SchemaError::Lock { path: path.display().to_string(), source };
+        let file = OpenOptions::new().create(true).truncate(false).write(true).open(&path).map_err(lock_error)?;
+
+        let deadline = Instant::now() + timeout;
+        let mut waiting = false;
+        while let Err(e) = file.try_lock_exclusive() {
+            if e.kind() != fs2::lock_contended_error().kind() {
+                return Err(lock_error(e));
+            }
+            if Instant::now() >= deadline {
+                return Err(SchemaError::LockTimeout(timeout));
+            }
+            if !waiting {
+                warn!("Waiting for another process migrating {}", filename.display());
+                waiting = true;
+            }
+            tokio::time::sleep(LOCK_POLL).await;
+        }
+        Ok(Self { file: Some(file) })
+    }
+}
+
+impl Drop for SqliteLock {
+    fn drop(&mut self) {
+        if let Some(file) = &self.file {
+            let _ = file.unlock();
+        }
+    }
+}
+
+/// Lock file of a SQLite database file; none for in-memory databases
+fn lock_path(database: &Path) -> Option<PathBuf> {
+    let name = database.to_str()?;
+    if name.is_empty() || name == ":memory:" || name.starts_with("file:") {
+        return None;
+    }
+    let mut path = database.as_os_str().to_owned();
+    path.push(".migrate.lock");
+    Some(PathBuf::from(path))
+}
@@ -1,15 +1,41 @@
 //! Runtime database queries for multi-database support
+//!
+//! SQLite databases are opened with a reader pool and a single-connection
+//! writer pool in WAL mode (see [`hal9_core::sqlite`]). Writes are serialized
+//! and retried with jittered backoff when SQLite reports the database busy,
+//! so concurrent signal logging, cost and memory writes queue instead of
+//! failing with `database is locked`.
+//!
+//! With a [`TenantKeyring`] the content of signals attributed to an
+//! organization with encryption enabled is sealed in the signal log and
+//! unsealed by [`RuntimeDatabase::get_signal`].
+
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
 
 use anyhow::Result;
 use chrono::{DateTime, Utc};
+use hal9_core::encryption::{self, TenantKeyring};
+use hal9_core::metadata_schema::keys;
+use hal9_core::sqlite::{self, CompactReport, PoolStats, SqlitePools, SqliteTuning, WriteGate};
 use hal9_core::NeuronSignal;
+use sqlx::any::{AnyPoolOptions, AnyRow};
 use sqlx::{AnyPool, Row};
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
 use uuid::Uuid;
 
 /// Database operations that work with both SQLite and PostgreSQL
 pub struct RuntimeDatabase {
+    /// Pool for reads. For PostgreSQL it also serves writes.
     pool: AnyPool,
+    /// Pool writes go through; a single connection for SQLite
+    writer: AnyPool,
+    gate: Arc<WriteGate>,
+    tuning: SqliteTuning,
     db_type: DatabaseType,
+    keyring: Option<Arc<TenantKeyring>>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -19,8 +45,113 @@ pub enum DatabaseType {
 }
 
 impl RuntimeDatabase {
+    /// Use one existing pool for reads and writes
     pub fn new(pool: AnyPool, db_type: DatabaseType) -> Self {
-        Self { pool, db_type }
+        Self {
+            writer: pool.clone(),
+            pool,
+            gate: Arc::new(WriteGate::default()),
+            tuning: SqliteTuning::default(),
+            db_type,
+            keyring: None,
+        }
+    }
+    
+    /// Seal the content of signals of organizations `keyring` encrypts
+    pub fn with_keyring(mut self, keyring: Arc<TenantKeyring>) -> Self {
+        self.keyring = Some(keyring);
+        self
+    }
+    
+    /// Connect to `url`. SQLite databases get tuned reader and writer pools
+    /// and must pass `PRAGMA quick_check` before use.
+    pub async fn connect(url: &str, db_type: DatabaseType, tuning: SqliteTuning) -> Result<Self> {
+        sqlx::any::install_default_drivers();
+        let acquire_timeout = Duration::from_millis(tuning.acquire_timeout_ms);
+        
+        if db_type == DatabaseType::Postgres {
+            let pool = AnyPoolOptions::new()
+                .acquire_timeout(acquire_timeout)
+                .connect(url)
+                .await?;
+            return Ok(Self::new(pool, db_type));
+        }
+        
+        let pragmas = Arc::new(tuning.pragmas());
+        let options = |max_connections: u32| {
+            let pragmas = pragmas.clone();
+            AnyPoolOptions::new()
+                .max_connections(max_connections)
+                .acquire_timeout(acquire_timeout)
+                .after_connect(move |conn, _meta| {
+                    let pragmas = pragmas.clone();
+                    Box::pin(async move {
+                        for pragma in pragmas.iter() {
+                            sqlx::query(pragma).execute(&mut *conn).await?;
+                        }
+                        Ok(())
+                    })
+                })
+        };
+        
+        let writer = options(1).connect(url).await?;
+        // Every connection to an in-memory database sees its own database
+        let pool = if url.contains(":memory:") || url.contains("mode=memory") {
+            writer.clone()
+        } else {
+            options(tuning.max_readers).connect(url).await?
+        };
+        
+        let database = Self {
+            pool,
+            writer,
+            gate: Arc::new(WriteGate::default()),
+            tuning,
+            db_type,
+            keyring: None,
+        };
+        database.quick_check(url).await?;
+        info!("Opened SQLite database {} in WAL mode", url);
+        Ok(database)
+    }
+    
+    /// Run a write on the writer pool. SQLite writes are serialized and
+    /// retried with jittered backoff while the database is busy.
+    pub async fn write<T, F, Fut>(&self, mut op: F) -> std::result::Result<T, sqlx::Error>
+    where
+        F: FnMut(AnyPool) -> Fut,
+        Fut: Future<Output = std::result::Result<T, sqlx::Error>>,
+    {
+        match self.db_type {
+            DatabaseType::Sqlite => self.gate.run(&self.tuning, || op(self.writer.clone())).await,
+            DatabaseType::Postgres => op(self.writer.clone()).await,
+        }
+    }
+    
+    /// Fail with a message naming the problems if `PRAGMA quick_check`
+    /// finds the SQLite database damaged
+    pub async fn quick_check(&self, database: &str) -> Result<()> {
+        if self.db_type != DatabaseType::Sqlite {
+            return Ok(());
+        }
+        let rows: Vec<String> = sqlx::query_scalar(sqlite::QUICK_CHECK)
+            .fetch_all(&self.pool)
+            .await?;
+        sqlite::check_integrity(database, rows)?;
+        Ok(())
+    }
+    
+    /// Connection usage and write contention
+    pub fn pool_stats(&self) -> PoolStats {
+        let mut stats = PoolStats {
+            reader_in_use: self.pool.size() - self.pool.num_idle() as u32,
+            reader_idle: self.pool.num_idle() as u32,
+            writer_in_use: self.writer.size() - self.writer.num_idle() as u32,
+            writer_idle: self.writer.num_idle() as u32,
+            ..Default::default()
+        };
+        self.gate.fill(&mut stats);
+        stats
     }
     
     /// Insert a signal into the database
@@ -36,16 +167,26 @@ impl RuntimeDatabase {
             }
         };
         
-        sqlx::query(query)
-            .bind(signal.signal_id.to_string())
-            .bind(&signal.from_neuron)
-            .bind(&signal.to_neuron)
-            .bind(&signal.layer_from)
-            .bind(&signal.layer_to)
-            .bind(&signal.payload.activation.content)
-            .bind(signal.timestamp.timestamp())
-            .execute(&self.pool)
-            .await?;
+        let content = &match &self.keyring {
+            Some(keyring) => {
+                let org_id = signal.metadata.get(keys::auth::ORG_ID).map(String::as_str);
+                keyring.seal(org_id, &signal.payload.activation.content).await?
+            }
+            None => signal.payload.activation.content.clone(),
+        };
+        self.write(|pool| async move {
+            sqlx::query(query)
+                .bind(signal.signal_id.to_string())
+                .bind(&signal.from_neuron)
+                .bind(&signal.to_neuron)
+                .bind(&signal.layer_from)
+                .bind(&signal.layer_to)
+                .bind(content)
+                .bind(signal.timestamp.timestamp())
+                .execute(&pool)
+                .await
+        })
+        .await?;
             
         Ok(())
     }
@@ -61,24 +202,28 @@ impl RuntimeDatabase {
             }
         };
         
-        sqlx::query(query)
-            .bind(state)
-            .bind(neuron_id.to_string())
-            .execute(&self.pool)
-            .await?;
+        let state = &state;
+        self.write(|pool| async move {
+            sqlx::query(query)
+                .bind(state)
+                .bind(neuron_id.to_string())
+                .execute(&pool)
+                .await
+        })
+        .await?;
             
         Ok(())
     }
     
     /// Get signal by ID
-    pub async fn get_signal(&self, signal_id: Uuid) -> Result<Option<SignalRecord>> {
+    pub async fn get_signal(&self, signal_id: Uuid) -> Result<Option<LoggedSignal>> {
         let query = match self.db_type {
             DatabaseType::Sqlite => {
-                "SELECT id, content, source, target, signal_type, priority, created_at, parent_id 
+                "SELECT id, from_neuron, to_neuron, layer_from, layer_to, content, timestamp 
                  FROM signals WHERE id = ?1"
             }
             DatabaseType::Postgres => {
-                "SELECT id, content, source, target, signal_type, priority, created_at, parent_id 
+                "SELECT id, from_neuron, to_neuron, layer_from, layer_to, content, timestamp 
                  FROM signals WHERE id = $1"
             }
         };
@@ -88,24 +233,95 @@ impl RuntimeDatabase {
             .fetch_optional(&self.pool)
             .await?;
             
-        if let Some(row) = row {
-            Ok(Some(SignalRecord {
-                id: row.try_get::<String, _>("id")?.parse()?,
-                content: row.try_get("content")?,
-                source: row.try_get("source")?,
-                target: row.try_get("target")?,
-                signal_type: row.try_get("signal_type")?,
-                priority: row.try_get("priority")?,
-                created_at: {
-                    let timestamp: i64 = row.try_get("created_at")?;
-                    DateTime::from_timestamp(timestamp, 0).unwrap_or_else(Utc::now)
-                },
-                parent_id: row.try_get::<Option<String>, _>("parent_id")?
-                    .map(|s| s.parse()).transpose()?,
-            }))
-        } else {
-            Ok(None)
+        match row {
+            Some(row) => {
+                let mut signal = LoggedSignal::from_row(&row)?;
+                signal.content = self.unseal(&signal.id, &signal.content).await?;
+                Ok(Some(signal))
+            }
+            None => Ok(None),
+        }
+    }
+    
+    /// Plaintext of a logged signal's content. Content sealed under a
+    /// retired key is resealed under the current one on the way.
+    async fn unseal(&self, id: &str, stored: &str) -> Result<String> {
+        if !encryption::is_sealed(stored) {
+            return Ok(stored.to_string());
+        }
+        let Some(keyring) = &self.keyring else {
+            anyhow::bail!("Signal {} is encrypted but no key store is configured", id);
+        };
+        if let Some(resealed) = keyring.reseal(stored)? {
+            let resealed = [(id.to_string(), stored.to_string(), resealed)];
+            if let Err(e) = self.store_resealed(&resealed).await {
+                warn!("Failed to reseal signal {}: {}", id, e);
+            }
         }
+        Ok(keyring.unseal(stored)?)
+    }
+    
+    /// Replace content that has not changed since it was read with its
+    /// resealed form, returning how many signals were updated
+    async fn store_resealed(&self, resealed: &[(String, String, String)]) -> Result<u64> {
+        let query = match self.db_type {
+            DatabaseType::Sqlite => "UPDATE signals SET content = ?1 WHERE id = ?2 AND content = ?3",
+            DatabaseType::Postgres => "UPDATE signals SET content = $1 WHERE id = $2 AND content = $3",
+        };
+        
+        let updated = self.write(|pool| async move {
+            let mut tx = pool.begin().await?;
+            let mut updated = 0;
+            for (id, old, new) in resealed {
+                updated += sqlx::query(query).bind(new).bind(id).bind(old).execute(&mut *tx).await?.rows_affected();
+            }
+            tx.commit().await?;
+            Ok(updated)
+        })
+        .await?;
+        
+        Ok(updated)
+    }
+    
+    /// Reseal up to `limit` signals whose content is sealed under a retired
+    /// key, returning how many were resealed
+    pub async fn reseal_signals(&self, limit: i64) -> Result<u64> {
+        let Some(keyring) = &self.keyring else {
+            return Ok(0);
+        };
+        let retired = keyring.retired_keys();
+        if retired.is_empty() {
+            return Ok(0);
+        }
+        
+        let conditions: Vec<String> = (1..=retired.len())
+            .map(|i| match self.db_type {
+                DatabaseType::Sqlite => format!("content LIKE ?{}", i),
+                DatabaseType::Postgres => format!("content LIKE ${}", i),
+            })
+            .collect();
+        let limit_param = match self.db_type {
+            DatabaseType::Sqlite => format!("?{}", retired.len() + 1),
+            DatabaseType::Postgres => format!("${}", retired.len() + 1),
+        };
+        let query = format!("SELECT id, content FROM signals WHERE {} LIMIT {}", conditions.join(" OR "), limit_param);
+        let mut sql_query = sqlx::query(&query);
+        for key_id in &retired {
+            sql_query = sql_query.bind(format!("{}%", encryption::sealed_prefix(key_id)));
+        }
+        let rows = sql_query.bind(limit).fetch_all(&self.pool).await?;
+        
+        let mut resealed = Vec::new();
+        for row in rows {
+            let (id, content): (String, String) = (row.try_get("id")?, row.try_get("content")?);
+            if let Some(new) = keyring.reseal(&content)? {
+                resealed.push((id, content, new));
+            }
+        }
+        if resealed.is_empty() {
+            return Ok(0);
+        }
+        self.store_resealed(&resealed).await
     }
     
     /// Clean old signals
@@ -119,14 +335,106 @@ impl RuntimeDatabase {
             }
         };
         
-        let result = sqlx::query(query)
-            .bind(days)
-            .execute(&self.pool)
-            .await?;
+        let result = self.write(|pool| async move {
+            sqlx::query(query)
+                .bind(days)
+                .execute(&pool)
+                .await
+        })
+        .await?;
             
         Ok(result.rows_affected())
     }
     
+    /// Logged signals older than `cutoff`, oldest first, a page at a time.
+    /// Content is returned as stored, so sealed signals stay sealed in archives.
+    pub async fn signals_before(&self, cutoff: DateTime<Utc>, limit: i64, offset: i64) -> Result<Vec<LoggedSignal>> {
+        let query = match self.db_type {
+            DatabaseType::Sqlite => {
+                "SELECT id, from_neuron, to_neuron, layer_from, layer_to, content, timestamp FROM signals
+                 WHERE timestamp < ?1 ORDER BY timestamp, id LIMIT ?2 OFFSET ?3"
+            }
+            DatabaseType::Postgres => {
+                "SELECT id, from_neuron, to_neuron, layer_from, layer_to, content, timestamp FROM signals
+                 WHERE timestamp < $1 ORDER BY timestamp, id LIMIT $2 OFFSET $3"
+            }
+        };
+        
+        let rows = sqlx::query(query)
+            .bind(cutoff.timestamp())
+            .bind(limit)
+            .bind(offset)
+            .fetch_all(&self.pool)
+            .await?;
+        
+        Ok(rows.iter().map(LoggedSignal::from_row).collect::<Result<_, _>>()?)
+    }
+    
+    /// Delete logged signals by ID, in one transaction
+    pub async fn delete_signals(&self, ids: &[String]) -> Result<u64> {
+        let query = match self.db_type {
+            DatabaseType::Sqlite => "DELETE FROM signals WHERE id = ?1",
+            DatabaseType::Postgres => "DELETE FROM signals WHERE id = $1",
+        };
+        
+        let deleted = self.write(|pool| async move {
+            let mut tx = pool.begin().await?;
+            let mut deleted = 0;
+            for id in ids {
+                deleted += sqlx::query(query).bind(id).execute(&mut *tx).await?.rows_affected();
+            }
+            tx.commit().await?;
+            Ok(deleted)
+        })
+        .await?;
+        
+        Ok(deleted)
+    }
+    
+    /// Delete cost records older than `cutoff`
+    pub async fn delete_cost_records_before(&self, cutoff: DateTime<Utc>) -> Result<u64> {
+        let query = match self.db_type {
+            DatabaseType::Sqlite => {
+                "DELETE FROM metrics_aggregate WHERE metric_name = 'cost_usd' AND timestamp < ?1"
+            }
+            DatabaseType::Postgres => {
+                "DELETE FROM metrics_aggregate WHERE metric_name = 'cost_usd' AND timestamp < $1"
+            }
+        };
+        
+        let result = self.write(|pool| async move {
+            sqlx::query(query)
+                .bind(cutoff.timestamp())
+                .execute(&pool)
+                .await
+        })
+        .await?;
+        
+        Ok(result.rows_affected())
+    }
+    
+    /// Return the pages freed by deletions to the filesystem and refresh
+    /// the planner's statistics (see [`sqlite::COMPACT`]). PostgreSQL
+    /// reclaims space through autovacuum, so only SQLite is compacted.
+    pub async fn compact(&self, database: &str) -> Result<CompactReport> {
+        if self.db_type != DatabaseType::Sqlite {
+            anyhow::bail!("Compaction is only needed for SQLite; PostgreSQL autovacuums {}", database);
+        }
+        let started = std::time::Instant::now();
+        let bytes_before: i64 = sqlx::query_scalar(sqlite::DATABASE_SIZE).fetch_one(&self.writer).await?;
+        for statement in sqlite::COMPACT {
+            self.write(|pool| async move { sqlx::query(statement).execute(&pool).await }).await?;
+        }
+        let bytes_after: i64 = sqlx::query_scalar(sqlite::DATABASE_SIZE).fetch_one(&self.writer).await?;
+        info!("Compacted SQLite {} from {} to {} bytes", database, bytes_before, bytes_after);
+        Ok(CompactReport {
+            database: database.to_string(),
+            bytes_before,
+            bytes_after,
+            duration_ms: started.elapsed().as_millis() as u64,
+        })
+    }
+    
     /// Insert audit log
     pub async fn insert_audit_log(&self, log: &AuditLog) -> Result<()> {
         let query = match self.db_type {
@@ -140,23 +448,56 @@ impl RuntimeDatabase {
             }
         };
         
-        sqlx::query(query)
-            .bind(log.id.to_string())
-            .bind(log.organization_id.to_string())
-            .bind(log.user_id.as_ref().map(|u| u.to_string()))
-            .bind(&log.action)
-            .bind(&log.resource_type)
-            .bind(log.resource_id.as_ref())
-            .bind(serde_json::to_string(&log.details)?)
-            .bind(log.ip_address.as_ref())
-            .bind(log.user_agent.as_ref())
-            .bind(log.timestamp.timestamp())
-            .execute(&self.pool)
-            .await?;
+        let details = &serde_json::to_string(&log.details)?;
+        self.write(|pool| async move {
+            sqlx::query(query)
+                .bind(log.id.to_string())
+                .bind(log.organization_id.to_string())
+                .bind(log.user_id.as_ref().map(|u| u.to_string()))
+                .bind(&log.action)
+                .bind(&log.resource_type)
+                .bind(log.resource_id.as_ref())
+                .bind(details)
+                .bind(log.ip_address.as_ref())
+                .bind(log.user_agent.as_ref())
+                .bind(log.timestamp.timestamp())
+                .execute(&pool)
+                .await
+        })
+        .await?;
             
         Ok(())
     }
     
+    /// Record the cost of a neuron's response in the metrics table
+    pub async fn record_cost(&self, neuron_id: &str, model: &str, cost_usd: f64) -> Result<()> {
+        let query = match self.db_type {
+            DatabaseType::Sqlite => {
+                "INSERT INTO metrics_aggregate (id, metric_name, neuron_id, value, labels, timestamp) 
+                 VALUES (?1, 'cost_usd', ?2, ?3, ?4, ?5)"
+            }
+            DatabaseType::Postgres => {
+                "INSERT INTO metrics_aggregate (id, metric_name, neuron_id, value, labels, timestamp) 
+                 VALUES ($1, 'cost_usd', $2, $3, $4, $5)"
+            }
+        };
+        
+        let labels = &serde_json::json!({ "model": model }).to_string();
+        self.write(|pool| async move {
+            sqlx::query(query)
+                .bind(Uuid::new_v4().to_string())
+                .bind(neuron_id)
+                .bind(cost_usd)
+                .bind(labels)
+                .bind(Utc::now().timestamp())
+                .execute(&pool)
+                .await
+        })
+        .await?;
+        
+        Ok(())
+    }
+    
     /// Get audit logs for organization
     pub async fn get_audit_logs(
         &self,
@@ -204,17 +545,48 @@ impl RuntimeDatabase {
     }
 }
 
-/// Signal record from database
-#[derive(Debug, Clone)]
-pub struct SignalRecord {
-    pub id: Uuid,
+/// A database whose connection usage is reported in metrics
+pub trait PoolStatsSource: Send + Sync {
+    fn pool_stats(&self) -> PoolStats;
+}
+
+impl PoolStatsSource for RuntimeDatabase {
+    fn pool_stats(&self) -> PoolStats {
+        RuntimeDatabase::pool_stats(self)
+    }
+}
+
+impl PoolStatsSource for SqlitePools {
+    fn pool_stats(&self) -> PoolStats {
+        self.stats()
+    }
+}
+
+/// Row of the signal log, as archived before it is deleted
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LoggedSignal {
+    pub id: String,
+    pub from_neuron: String,
+    pub to_neuron: String,
+    pub layer_from: String,
+    pub layer_to: String,
     pub content: String,
-    pub source: String,
-    pub target: String,
-    pub signal_type: String,
-    pub priority: i32,
-    pub created_at: DateTime<Utc>,
-    pub parent_id: Option<Uuid>,
+    /// Unix seconds
+    pub timestamp: i64,
+}
+
+impl LoggedSignal {
+    fn from_row(row: &AnyRow) -> Result<Self, sqlx::Error> {
+        Ok(Self {
+            id: row.try_get("id")?,
+            from_neuron: row.try_get("from_neuron")?,
+            to_neuron: row.try_get("to_neuron")?,
+            layer_from: row.try_get("layer_from")?,
+            layer_to: row.try_get("layer_to")?,
+            content: row.try_get("content")?,
+            timestamp: row.try_get("timestamp")?,
+        })
+    }
 }
 
 /// Audit log record
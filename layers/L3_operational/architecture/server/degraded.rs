@@ -0,0 +1,345 @@
+//! Graceful degradation while every provider is down
+//!
+//! Once the circuit breaker of every neuron is open the server enters
+//! degraded mode, unless `degraded.auto` is off; operators may also force it
+//! in or out. While degraded, neurons of the `degraded.decompose_layers`
+//! split their task with fixed templates instead of calling their provider,
+//! as the MVP's mock neurons did, and signals for any other layer are parked
+//! rather than failed. A chain with parked signals keeps running and lists
+//! them as deferred branches.
+//!
+//! Every `degraded.probe_interval_ms` the oldest parked signal is retried
+//! against its provider. Once one gets through, degraded mode ends and the
+//! parked signals are sent back to the router in the order they were parked.
+//! A forced degraded mode only ends when forced out.
+
+use std::collections::{HashSet, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use parking_lot::Mutex;
+use serde::Serialize;
+use tokio::sync::mpsc;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+use hal9_core::config::DegradedConfig;
+use hal9_core::{NeuronSignal, PropagationType};
+
+use crate::circuit_breaker::CircuitState;
+use crate::error::{ServerError, ServerResult};
+use crate::neuron::NeuronRegistry;
+
+/// Reason recorded on the branches parked while degraded
+pub const DEFERRED_REASON: &str = "deferred: provider outage";
+
+/// Model recorded on steps answered with templates while degraded
+pub const HEURISTIC_MODEL: &str = "local-heuristic";
+
+/// Actor recorded when degraded mode is entered or left on its own
+pub const AUTO_ACTOR: &str = "auto";
+
+/// Why the server is degraded
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DegradedTrigger {
+    /// Every circuit breaker is open
+    Outage,
+    /// An operator forced it
+    Forced,
+}
+
+/// Degraded mode, as reported
+#[derive(Debug, Clone, Serialize)]
+pub struct DegradedStatus {
+    pub active: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trigger: Option<DegradedTrigger>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub since: Option<DateTime<Utc>>,
+    /// Who forced degraded mode, when it was forced
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub forced_by: Option<String>,
+    /// Signals waiting for providers to recover
+    pub parked: usize,
+    /// Signals dead-lettered because too many were parked
+    pub spilled: u64,
+}
+
+/// What the router should do with a signal for a neuron of some layer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DegradedAdmission {
+    /// Call the provider as usual
+    Process,
+    /// A parked signal retried to see whether providers recovered
+    Probe,
+    /// Answer with the configured templates
+    Decompose,
+    /// Park until providers recover
+    Park,
+}
+
+struct State {
+    trigger: Option<DegradedTrigger>,
+    since: Option<DateTime<Utc>>,
+    forced_by: Option<String>,
+    parked: VecDeque<NeuronSignal>,
+    /// Parked signals sent back to retry their provider
+    probes: HashSet<Uuid>,
+}
+
+/// Degraded mode and the signals parked during it
+pub struct DegradedMode {
+    config: DegradedConfig,
+    state: Mutex<State>,
+    /// Router queue released signals are sent back to
+    release_tx: Mutex<Option<mpsc::Sender<NeuronSignal>>>,
+    spilled: AtomicU64,
+}
+
+impl DegradedMode {
+    pub fn new(config: DegradedConfig) -> Self {
+        Self {
+            config,
+            state: Mutex::new(State {
+                trigger: None,
+                since: None,
+                forced_by: None,
+                parked: VecDeque::new(),
+                probes: HashSet::new(),
+            }),
+            release_tx: Mutex::new(None),
+            spilled: AtomicU64::new(0),
+        }
+    }
+
+    /// Send released signals to the router's queue
+    pub fn attach(&self, release_tx: mpsc::Sender<NeuronSignal>) {
+        *self.release_tx.lock() = Some(release_tx);
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.state.lock().trigger.is_some()
+    }
+
+    /// How to handle `signal`, addressed to a neuron of `layer`. Backward
+    /// signals never reach a provider and are always processed.
+    pub fn admit(&self, signal: &NeuronSignal, layer: &str) -> DegradedAdmission {
+        let mut state = self.state.lock();
+        if state.probes.remove(&signal.signal_id) {
+            return DegradedAdmission::Probe;
+        }
+        if state.trigger.is_none() || signal.propagation_type == PropagationType::Backward {
+            return DegradedAdmission::Process;
+        }
+        if self.config.decompose_layers.iter().any(|l| l == layer) {
+            DegradedAdmission::Decompose
+        } else {
+            DegradedAdmission::Park
+        }
+    }
+
+    /// The templated tasks a decomposing neuron forwards for `task`
+    pub fn tasks(&self, task: &str) -> Vec<String> {
+        self.config.templates.iter().map(|template| template.replace("{task}", task)).collect()
+    }
+
+    /// Park a signal until providers recover. A probe that failed goes back
+    /// to the front, keeping the order signals were parked in. Gives the
+    /// signal back when too many are parked.
+    pub fn park(&self, signal: NeuronSignal, probe: bool) -> Result<(), Box<NeuronSignal>> {
+        let mut state = self.state.lock();
+        if probe {
+            state.parked.push_front(signal);
+            return Ok(());
+        }
+        if state.parked.len() >= self.config.max_parked {
+            self.spilled.fetch_add(1, Ordering::Relaxed);
+            return Err(Box::new(signal));
+        }
+        state.parked.push_back(signal);
+        Ok(())
+    }
+
+    /// Enter degraded mode if a failure left every breaker open. Returns
+    /// whether the server is degraded.
+    pub async fn check_outage(&self, registry: &NeuronRegistry) -> bool {
+        if self.is_active() {
+            return true;
+        }
+        if !self.config.auto || !all_breakers_open(registry).await {
+            return false;
+        }
+
+        let mut state = self.state.lock();
+        if state.trigger.is_none() {
+            state.trigger = Some(DegradedTrigger::Outage);
+            state.since = Some(Utc::now());
+            state.forced_by = None;
+            warn!(
+                target: "audit",
+                event = "degraded_entered",
+                trigger = "outage",
+                actor = AUTO_ACTOR,
+                "Every provider is down, entering degraded mode"
+            );
+        }
+        true
+    }
+
+    /// Leave an outage's degraded mode once a breaker is no longer open,
+    /// releasing the parked signals. Returns how many were released.
+    pub async fn check_recovery(&self, registry: &NeuronRegistry) -> usize {
+        if self.state.lock().trigger != Some(DegradedTrigger::Outage) {
+            return 0;
+        }
+        if all_breakers_open(registry).await {
+            return 0;
+        }
+        self.leave(Some(DegradedTrigger::Outage), AUTO_ACTOR).await
+    }
+
+    /// Retry the oldest parked signal against its provider, unless
+    /// providers already recovered
+    pub async fn probe(&self, registry: &NeuronRegistry) {
+        if self.check_recovery(registry).await > 0 {
+            return;
+        }
+        let Some(release_tx) = self.release_tx.lock().clone() else {
+            return;
+        };
+        let probe = {
+            let mut state = self.state.lock();
+            if state.trigger != Some(DegradedTrigger::Outage) {
+                return;
+            }
+            let Some(signal) = state.parked.pop_front() else {
+                return;
+            };
+            state.probes.insert(signal.signal_id);
+            signal
+        };
+        if release_tx.send(probe).await.is_err() {
+            error!("Router stopped while probing providers with a parked signal");
+        }
+    }
+
+    /// Force degraded mode on until forced off
+    pub fn force_enter(&self, actor: Option<&str>) -> DegradedStatus {
+        {
+            let mut state = self.state.lock();
+            if state.trigger.is_none() {
+                state.since = Some(Utc::now());
+            }
+            state.trigger = Some(DegradedTrigger::Forced);
+            state.forced_by = actor.map(str::to_string);
+        }
+        info!(
+            target: "audit",
+            event = "degraded_entered",
+            trigger = "forced",
+            actor = actor.unwrap_or("unknown"),
+            "Degraded mode forced on"
+        );
+        self.status()
+    }
+
+    /// Leave degraded mode, however it was entered, releasing the parked
+    /// signals. Returns how many were released. A failure that finds every
+    /// breaker still open enters it again.
+    pub async fn force_exit(&self, actor: Option<&str>) -> ServerResult<usize> {
+        if !self.is_active() {
+            return Err(ServerError::InvalidInput("Degraded mode is not active".to_string()));
+        }
+        Ok(self.leave(None, actor.unwrap_or("unknown")).await)
+    }
+
+    /// Leave degraded mode if entered by `trigger` (any trigger if `None`)
+    /// and send the parked signals back to the router in order
+    async fn leave(&self, trigger: Option<DegradedTrigger>, actor: &str) -> usize {
+        let release_tx = self.release_tx.lock().clone();
+        let parked = {
+            let mut state = self.state.lock();
+            if state.trigger.is_none() || trigger.is_some_and(|t| state.trigger != Some(t)) {
+                return 0;
+            }
+            if !state.parked.is_empty() && release_tx.is_none() {
+                error!("Degraded mode holds parked signals but no router is attached");
+                return 0;
+            }
+            state.trigger = None;
+            state.since = None;
+            state.forced_by = None;
+            std::mem::take(&mut state.parked)
+        };
+
+        let released = parked.len();
+        info!(
+            target: "audit",
+            event = "degraded_left",
+            actor = actor,
+            released,
+            "Leaving degraded mode"
+        );
+        if let Some(release_tx) = release_tx {
+            for signal in parked {
+                if release_tx.send(signal).await.is_err() {
+                    error!("Router stopped while releasing parked signals");
+                    break;
+                }
+            }
+        }
+        released
+    }
+
+    /// Probe for recovered providers while degraded by an outage
+    pub fn start(self: &Arc<Self>, registry: Arc<NeuronRegistry>) {
+        let degraded = self.clone();
+        let period = Duration::from_millis(self.config.probe_interval_ms.max(1));
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(period);
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+            loop {
+                interval.tick().await;
+                degraded.probe(&registry).await;
+            }
+        });
+    }
+
+    pub fn status(&self) -> DegradedStatus {
+        let state = self.state.lock();
+        DegradedStatus {
+            active: state.trigger.is_some(),
+            trigger: state.trigger,
+            since: state.since,
+            forced_by: state.forced_by.clone(),
+            parked: state.parked.len(),
+            spilled: self.spilled.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Describe the tasks a neuron forwarded while degraded, as its step's output
+pub fn describe(children: &[NeuronSignal]) -> String {
+    let mut output = format!("DEGRADED: split into {} tasks with local templates", children.len());
+    for child in children {
+        output.push_str(&format!("\n- {}: {}", child.to_neuron, child.payload.activation.content));
+    }
+    output
+}
+
+/// Whether the breaker of every registered neuron is open
+async fn all_breakers_open(registry: &NeuronRegistry) -> bool {
+    let neurons = registry.all();
+    if neurons.is_empty() {
+        return false;
+    }
+    for neuron in neurons {
+        if neuron.circuit_state().await != CircuitState::Open {
+            return false;
+        }
+    }
+    true
+}
@@ -0,0 +1,395 @@
+//! Local developer mode
+//!
+//! `hal9 dev` runs a whole stack in one process, offline and without a
+//! config file: Claude in mock mode, authentication and memory in in-memory
+//! SQLite, and the L4/L3/L2 trio of [`default_config`]. Demo users are
+//! created with an API key each, for the CLI to print.
+//!
+//! The MVP demo's scenarios ship as mock scenario files, written to a
+//! temporary directory at boot. The one asked for is replayed from the
+//! start, and [`DevStack::run_scenario`] starts its chain. The topology is
+//! read from a local `topology.yaml` when there is one, and
+//! [`watch_topology`] applies edits to that file as topology patches while
+//! the stack runs.
+//!
+//! Nothing is kept: every boot starts from the same state.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use serde::{Deserialize, Serialize};
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use hal9_core::auth::{CreateApiKeyRequest, CreateUserRequest, UserRole};
+use hal9_core::config::{ClaudeConfig, MockResponse, MonitoringConfig, NetworkConfig};
+use hal9_core::sqlite::{SqlitePools, SqliteTuning};
+use hal9_core::{NeuronConfig, NeuronSignal, ServerConfig};
+
+use crate::chain_tracker::{ChainResult, ChainStatus};
+use crate::error::{ServerError, ServerResult};
+use crate::server::HAL9Server;
+use crate::topology::{self, AppliedPatch};
+
+/// Server ID of the developer stack
+pub const DEV_SERVER_ID: &str = "hal9-dev";
+
+/// Topology file read and watched, relative to the working directory
+pub const TOPOLOGY_FILE: &str = "topology.yaml";
+
+/// How often the topology file is checked for changes
+pub const TOPOLOGY_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Whether this build serves the admin UI
+pub const ADMIN_UI: bool = cfg!(feature = "admin-ui");
+
+/// Password of every demo user
+pub const DEMO_PASSWORD: &str = "hal9-dev-password";
+
+/// Neuron the scenarios' chains start at
+pub const ENTRY_NEURON: &str = "neuron-l4-strategic";
+
+/// Demo scenarios: name, input of the chain and scenario file
+pub const SCENARIOS: &[(&str, &str, &str)] = &[
+    ("task-manager", "Create a task management web application", include_str!("dev/task-manager.yaml")),
+    ("e-commerce", "Build an e-commerce platform", include_str!("dev/e-commerce.yaml")),
+    ("chat", "Develop a real-time chat system", include_str!("dev/chat.yaml")),
+];
+
+/// Demo users, one per role
+const USERS: &[(&str, UserRole)] = &[
+    ("admin", UserRole::Admin),
+    ("developer", UserRole::User),
+    ("guest", UserRole::Guest),
+];
+
+/// Recorded as the actor of topology patches from the topology file
+const ACTOR: &str = "hal9 dev";
+
+/// What to boot
+#[derive(Debug, Clone, Default)]
+pub struct DevOptions {
+    /// Topology file; without one, or while it does not exist, the trio
+    pub topology: Option<PathBuf>,
+    /// Scenario to replay, by name in [`SCENARIOS`]
+    pub scenario: Option<String>,
+}
+
+/// A seeded user and the API key created for it
+#[derive(Debug, Clone, Serialize)]
+pub struct DemoUser {
+    pub username: String,
+    pub role: String,
+    pub password: String,
+    pub api_key: String,
+}
+
+/// A topology file: the `neurons` of a server config
+#[derive(Debug, Deserialize)]
+struct TopologyFile {
+    neurons: Vec<NeuronConfig>,
+}
+
+/// A started developer stack
+pub struct DevStack {
+    pub server: Arc<HAL9Server>,
+    pub users: Vec<DemoUser>,
+    /// Scenario replayed, with the input of its chain
+    pub scenario: Option<(&'static str, &'static str)>,
+    scenarios_dir: PathBuf,
+}
+
+impl DevStack {
+    /// Start the chain of the scenario being replayed and wait for it to
+    /// finish
+    pub async fn run_scenario(&self, timeout: Duration) -> ServerResult<ChainResult> {
+        let (name, input) = self
+            .scenario
+            .ok_or_else(|| ServerError::InvalidInput("No scenario was asked for".to_string()))?;
+        let signal = NeuronSignal::forward("hal9-dev", ENTRY_NEURON, "API", "L4", input.to_string());
+        let chain_id = self.server.submit_signal(signal).await?;
+        info!("Running scenario {} as chain {}", name, chain_id);
+
+        let wait = async {
+            loop {
+                let result = self.server.get_chain_result(&chain_id).await?;
+                if result.status != ChainStatus::Running {
+                    return Ok(result);
+                }
+                tokio::time::sleep(Duration::from_millis(50)).await;
+            }
+        };
+        tokio::time::timeout(timeout, wait)
+            .await
+            .map_err(|_| ServerError::Internal(format!("Scenario {} did not finish within {:?}", name, timeout)))?
+    }
+
+    /// Stop the server and remove the scenario files
+    pub async fn shutdown(self) -> ServerResult<()> {
+        self.server.shutdown().await.map_err(anyhow::Error::from)?;
+        if let Err(e) = std::fs::remove_dir_all(&self.scenarios_dir) {
+            warn!("Failed to remove {}: {}", self.scenarios_dir.display(), e);
+        }
+        Ok(())
+    }
+}
+
+/// Start the developer stack and seed its users
+pub async fn boot(options: &DevOptions) -> ServerResult<DevStack> {
+    let scenario = match &options.scenario {
+        Some(name) => Some(scenario(name)?),
+        None => None,
+    };
+    let neurons = match &options.topology {
+        Some(path) if path.exists() => read_topology(path)?,
+        _ => default_config().neurons,
+    };
+
+    let scenarios_dir = std::env::temp_dir().join(format!("hal9-dev-{}", Uuid::new_v4()));
+    std::fs::create_dir_all(&scenarios_dir)?;
+    for (name, _, file) in SCENARIOS {
+        std::fs::write(scenarios_dir.join(format!("{}.yaml", name)), file)?;
+    }
+
+    let mut config = default_config();
+    config.server_id = DEV_SERVER_ID.to_string();
+    config.neurons = neurons;
+    config.auth.enabled = true;
+    config.auth.database_path = ":memory:".to_string();
+    config.memory.enabled = true;
+    config.memory.database_path = ":memory:".to_string();
+    config.claude.scenarios.dir = Some(scenarios_dir.display().to_string());
+    config.claude.scenarios.active = scenario.map(|(name, _)| name.to_string());
+
+    let mut server = HAL9Server::new(config);
+    let auth_pools = SqlitePools::connect("sqlite::memory:", SqliteTuning::default())
+        .await
+        .map_err(anyhow::Error::from)?;
+    server.initialize_auth(auth_pools).await.map_err(anyhow::Error::from)?;
+    let server = Arc::new(server);
+    server.start().await.map_err(anyhow::Error::from)?;
+
+    let users = seed_users(&server).await?;
+    Ok(DevStack { server, users, scenario, scenarios_dir })
+}
+
+/// The scenario named `name`, with the input of its chain
+fn scenario(name: &str) -> ServerResult<(&'static str, &'static str)> {
+    SCENARIOS
+        .iter()
+        .find(|(scenario, _, _)| *scenario == name)
+        .map(|(name, input, _)| (*name, *input))
+        .ok_or_else(|| {
+            let names: Vec<&str> = SCENARIOS.iter().map(|(name, _, _)| *name).collect();
+            ServerError::InvalidInput(format!("Unknown scenario {}; one of {}", name, names.join(", ")))
+        })
+}
+
+/// Create the demo users, each with an API key of its role's permissions
+async fn seed_users(server: &HAL9Server) -> ServerResult<Vec<DemoUser>> {
+    let (Some(user_manager), Some(api_key_manager)) = (&server.user_manager, &server.api_key_manager) else {
+        return Err(ServerError::Internal("Authentication is not initialized".to_string()));
+    };
+    let mut users = Vec::with_capacity(USERS.len());
+    for (username, role) in USERS {
+        let user = user_manager
+            .create_user(CreateUserRequest {
+                username: username.to_string(),
+                email: format!("{}@hal9.dev", username),
+                password: DEMO_PASSWORD.to_string(),
+                role: Some(role.clone()),
+            })
+            .await
+            .map_err(|e| ServerError::Internal(format!("Failed to create demo user {}: {}", username, e)))?;
+        let key = api_key_manager
+            .create_api_key(&user.id, CreateApiKeyRequest {
+                name: format!("{}-dev", username),
+                permissions: role.default_permissions(),
+                ..Default::default()
+            })
+            .await
+            .map_err(|e| ServerError::Internal(format!("Failed to create an API key for {}: {}", username, e)))?;
+        users.push(DemoUser {
+            username: user.username,
+            role: user.role,
+            password: DEMO_PASSWORD.to_string(),
+            api_key: key.key,
+        });
+    }
+    Ok(users)
+}
+
+/// Neurons of a topology file
+pub fn read_topology(path: &Path) -> ServerResult<Vec<NeuronConfig>> {
+    let text = std::fs::read_to_string(path)?;
+    let file: TopologyFile = serde_yaml::from_str(&text)
+        .map_err(|e| ServerError::InvalidInput(format!("Invalid topology file {}: {}", path.display(), e)))?;
+    Ok(file.neurons)
+}
+
+/// Apply the topology file to the running neurons as a topology patch, or
+/// nothing if they already match it
+pub async fn reload_topology(server: &HAL9Server, path: &Path) -> ServerResult<Option<AppliedPatch>> {
+    let neurons = read_topology(path)?;
+    let ops = topology::ops_between(&server.topology(), &neurons);
+    if ops.is_empty() {
+        return Ok(None);
+    }
+    let proposal = server.propose_topology_patch(ops)?;
+    server.apply_topology_patch(&proposal.token, Some(ACTOR)).await.map(Some)
+}
+
+/// Reload the topology file whenever it changes, checking every `interval`.
+/// A file that does not load or breaks a topology rule is logged and left
+/// until it changes again.
+pub fn watch_topology(server: Arc<HAL9Server>, path: PathBuf, interval: Duration) -> JoinHandle<()> {
+    let modified = |path: &Path| -> Option<SystemTime> { std::fs::metadata(path).and_then(|m| m.modified()).ok() };
+    let mut seen = modified(&path);
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        ticker.tick().await;
+        loop {
+            ticker.tick().await;
+            let current = modified(&path);
+            if current.is_none() || current == seen {
+                continue;
+            }
+            seen = current;
+            match reload_topology(&server, &path).await {
+                Ok(Some(applied)) => info!(
+                    "Reloaded {}: topology version {} with {} changes",
+                    path.display(), applied.version, applied.diff.len()
+                ),
+                Ok(None) => info!("{} changed, but not the topology", path.display()),
+                Err(e) => warn!("Failed to reload {}: {}", path.display(), e),
+            }
+        }
+    })
+}
+
+/// Configuration of a server without a config file: mock Claude and the
+/// L4/L3/L2 trio, each forwarding to the next
+pub fn default_config() -> ServerConfig {
+    // Create mock responses for demo
+    let mut mock_responses = HashMap::new();
+
+    // L4 responses
+    mock_responses.insert("L4".to_string(), vec![
+        MockResponse {
+            trigger: "default".to_string(),
+            response: "FORWARD_TO: neuron-l3-design\nCONTENT: Breaking down the request into design requirements".to_string(),
+            delay_ms: 100,
+        },
+    ]);
+
+    // L3 responses
+    mock_responses.insert("L3".to_string(), vec![
+        MockResponse {
+            trigger: "default".to_string(),
+            response: "FORWARD_TO: neuron-l2-impl\nCONTENT: Creating implementation plan based on design".to_string(),
+            delay_ms: 100,
+        },
+    ]);
+
+    // L2 responses
+    mock_responses.insert("L2".to_string(), vec![
+        MockResponse {
+            trigger: "default".to_string(),
+            response: "RESULT: Implementation complete\n```python\n# Generated code\nprint('Hello from 2HAL9!')\n```".to_string(),
+            delay_ms: 100,
+        },
+    ]);
+
+    ServerConfig {
+        server_id: "hal9-server-1".to_string(),
+        neurons: vec![
+            NeuronConfig {
+                id: "neuron-l4-strategic".to_string(),
+                layer: "L4".to_string(),
+                claude_command: "claude".to_string(),
+                system_prompt: Some("You are a strategic layer neuron".to_string()),
+                forward_connections: vec!["neuron-l3-design".to_string()],
+                backward_connections: vec![],
+                settings: HashMap::new(),
+            },
+            NeuronConfig {
+                id: "neuron-l3-design".to_string(),
+                layer: "L3".to_string(),
+                claude_command: "claude".to_string(),
+                system_prompt: Some("You are a design layer neuron".to_string()),
+                forward_connections: vec!["neuron-l2-impl".to_string()],
+                backward_connections: vec!["neuron-l4-strategic".to_string()],
+                settings: HashMap::new(),
+            },
+            NeuronConfig {
+                id: "neuron-l2-impl".to_string(),
+                layer: "L2".to_string(),
+                claude_command: "claude".to_string(),
+                system_prompt: Some("You are an implementation layer neuron".to_string()),
+                forward_connections: vec![],
+                backward_connections: vec!["neuron-l3-design".to_string()],
+                settings: HashMap::new(),
+            },
+        ],
+        claude: ClaudeConfig {
+            mode: "mock".to_string(),
+            api_key: None,
+            model: "claude-3-sonnet-20240229".to_string(),
+            temperature: 0.7,
+            max_tokens: 4000,
+            rate_limit: 10,
+            mock_responses,
+            fallback_to_mock: false, // Not needed in mock mode
+            cost_controls: Default::default(),
+            scenarios: Default::default(),
+        },
+        monitoring: MonitoringConfig::default(),
+        network: NetworkConfig::default(),
+        memory: Default::default(),
+        backward_propagation: Default::default(),
+        auth: Default::default(),
+        browser: None,
+        limits: Default::default(),
+        webhooks: Default::default(),
+        scaling: Default::default(),
+        validation: Default::default(),
+        health: Default::default(),
+        metadata_validation: Default::default(),
+        goals: Default::default(),
+        receipts: Default::default(),
+        layer_pause: Default::default(),
+        simulation: Default::default(),
+        compression: Default::default(),
+        autoscaling: Default::default(),
+        output_format: Default::default(),
+        warm_pool: Default::default(),
+        recovery: Default::default(),
+        local_models: Default::default(),
+        isolation: Default::default(),
+        read_only: Default::default(),
+        retention: Default::default(),
+        timeouts: Default::default(),
+        models: Default::default(),
+        database: Default::default(),
+        fan_out: Default::default(),
+        singularity: Default::default(),
+        encryption: Default::default(),
+        speculation: Default::default(),
+        ingestion: Default::default(),
+        quality: Default::default(),
+        time_travel: Default::default(),
+        degraded: Default::default(),
+        report_privacy: Default::default(),
+        shadow: Default::default(),
+        prompt_composition: Default::default(),
+        approvals: Default::default(),
+        templates: Default::default(),
+        profiling: Default::default(),
+        sessions: Default::default(),
+        billing_export: Default::default(),
+        tool_sandbox: Default::default(),
+    }
+}
@@ -38,6 +38,7 @@ pub struct OrganizationSettings {
     pub max_api_calls_per_month: Option<u64>,
     pub max_neurons: Option<u32>,
     pub max_storage_gb: Option<u32>,
+    pub max_concurrent_chains: Option<u32>,
     
     /// Features
     pub enabled_features: Vec<String>,
@@ -58,6 +59,7 @@ impl Default for OrganizationSettings {
             max_api_calls_per_month: None,
             max_neurons: None,
             max_storage_gb: None,
+            max_concurrent_chains: None,
             enabled_features: vec![],
             custom_branding: None,
         }
@@ -25,6 +25,19 @@ pub enum ServerError {
     #[error("Configuration error: {0}")]
     ConfigError(String),
     
+    #[error("Limit exceeded: {0}")]
+    LimitExceeded(String),
+    
+    /// A tool call refused for a limit of its chain, which won't reset
+    #[error("{0}")]
+    ToolLimitExceeded(String),
+    
+    #[error("Forbidden: {0}")]
+    Forbidden(String),
+    
+    #[error("Server is draining and accepts no new chains")]
+    Draining,
+    
     #[error("IO error: {0}")]
     IoError(#[from] std::io::Error),
     
@@ -35,4 +48,15 @@ pub enum ServerError {
     Other(#[from] anyhow::Error),
 }
 
-pub type ServerResult<T> = Result<T, ServerError>;
\ No newline at end of file
+pub type ServerResult<T> = Result<T, ServerError>;
+
+impl ServerError {
+    /// Error of a tool call; refusals for its chain's tool limits keep
+    /// their kind
+    pub fn from_tool(error: hal9_core::Error) -> Self {
+        match error {
+            hal9_core::Error::ToolLimitExceeded { .. } => ServerError::ToolLimitExceeded(error.to_string()),
+            error => ServerError::Other(error.into()),
+        }
+    }
+}
\ No newline at end of file
@@ -2,7 +2,7 @@
 
 use axum::{
     extract::Request,
-    http::StatusCode,
+    http::{header, StatusCode},
     middleware::Next,
     response::{IntoResponse, Response},
     Json,
@@ -13,6 +13,7 @@ use std::{
     sync::Arc,
     time::{Duration, Instant},
 };
+use rand::Rng;
 use tokio::sync::RwLock;
 use tracing::{error, warn, debug};
 use uuid::Uuid;
@@ -20,8 +21,11 @@ use uuid::Uuid;
 use crate::{
     error::ServerError,
     middleware::extract_trace_id,
+    simulation::SimRng,
 };
 
+pub use hal9_api_types::{ErrorCode, ErrorDetails, ErrorResponse};
+
 /// Error context for detailed debugging
 #[derive(Debug, Clone, Serialize)]
 pub struct ErrorContext {
@@ -36,26 +40,6 @@ pub struct ErrorContext {
     pub metadata: HashMap<String, serde_json::Value>,
 }
 
-/// Error response format
-#[derive(Debug, Serialize)]
-pub struct ErrorResponse {
-    pub error: ErrorDetails,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub retry_after: Option<u64>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub help_url: Option<String>,
-}
-
-#[derive(Debug, Serialize)]
-pub struct ErrorDetails {
-    pub code: String,
-    pub message: String,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub error_id: Option<String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub details: Option<serde_json::Value>,
-}
-
 /// Recovery strategy for different error types
 #[derive(Clone)]
 pub enum RecoveryStrategy {
@@ -154,6 +138,9 @@ pub async fn error_recovery_middleware(
                 "Request failed with server error"
             );
             
+            // A retry hint the handler gave outlives the rewrite
+            let retry_after = result.headers().get(header::RETRY_AFTER).cloned();
+            
             // Create error response
             let error_response = ErrorResponse {
                 error: ErrorDetails {
@@ -162,7 +149,9 @@ pub async fn error_recovery_middleware(
                     error_id: Some(error_id.clone()),
                     details: None,
                 },
-                retry_after: calculate_retry_after(result.status()),
+                retry_after: retry_after.as_ref()
+                    .and_then(|value| value.to_str().ok()?.parse().ok())
+                    .or_else(|| calculate_retry_after(result.status())),
                 help_url: Some(format!("https://docs.2lab.ai/errors/{}", 
                     map_status_to_error_code(result.status()))),
             };
@@ -184,7 +173,11 @@ pub async fn error_recovery_middleware(
                 store.store_error(context).await;
             }
             
-            (result.status(), Json(error_response)).into_response()
+            let mut response = (result.status(), Json(error_response)).into_response();
+            if let Some(retry_after) = retry_after {
+                response.headers_mut().insert(header::RETRY_AFTER, retry_after);
+            }
+            response
         },
         false => result,
     };
@@ -198,6 +191,7 @@ pub struct RetryMiddleware {
     initial_delay: Duration,
     max_delay: Duration,
     jitter: bool,
+    rng: SimRng,
 }
 
 impl RetryMiddleware {
@@ -207,9 +201,16 @@ impl RetryMiddleware {
             initial_delay: Duration::from_millis(100),
             max_delay: Duration::from_secs(10),
             jitter: true,
+            rng: SimRng::from_entropy(),
         }
     }
     
+    /// Draw jitter from `rng`, e.g. a seeded simulation stream
+    pub fn with_rng(mut self, rng: SimRng) -> Self {
+        self.rng = rng;
+        self
+    }
+    
     pub async fn execute<F, T>(&self, mut operation: F) -> Result<T, ServerError>
     where
         F: FnMut() -> Result<T, ServerError>,
@@ -244,7 +245,7 @@ impl RetryMiddleware {
                     
                     // Apply jitter if enabled
                     let actual_delay = if self.jitter {
-                        let jitter = rand::random::<f64>() * 0.3; // Up to 30% jitter
+                        let jitter = self.rng.clone().gen::<f64>() * 0.3; // Up to 30% jitter
                         Duration::from_millis((delay.as_millis() as f64 * (1.0 + jitter)) as u64)
                     } else {
                         delay
@@ -293,6 +294,11 @@ impl ErrorStore {
         errors[start..].to_vec()
     }
     
+    /// Every stored error, oldest first
+    pub async fn all_errors(&self) -> Vec<ErrorContext> {
+        self.errors.read().await.clone()
+    }
+    
     pub async fn get_error_by_id(&self, error_id: &str) -> Option<ErrorContext> {
         let errors = self.errors.read().await;
         errors.iter().find(|e| e.error_id == error_id).cloned()
@@ -364,19 +370,7 @@ pub fn setup_panic_handler() {
 
 /// Helper functions
 fn map_status_to_error_code(status: StatusCode) -> String {
-    match status {
-        StatusCode::BAD_REQUEST => "BAD_REQUEST",
-        StatusCode::UNAUTHORIZED => "UNAUTHORIZED",
-        StatusCode::FORBIDDEN => "FORBIDDEN",
-        StatusCode::NOT_FOUND => "NOT_FOUND",
-        StatusCode::CONFLICT => "CONFLICT",
-        StatusCode::TOO_MANY_REQUESTS => "RATE_LIMITED",
-        StatusCode::INTERNAL_SERVER_ERROR => "INTERNAL_ERROR",
-        StatusCode::BAD_GATEWAY => "BAD_GATEWAY",
-        StatusCode::SERVICE_UNAVAILABLE => "SERVICE_UNAVAILABLE",
-        StatusCode::GATEWAY_TIMEOUT => "GATEWAY_TIMEOUT",
-        _ => "UNKNOWN_ERROR",
-    }.to_string()
+    ErrorCode::from_status(status.as_u16()).to_string()
 }
 
 fn calculate_retry_after(status: StatusCode) -> Option<u64> {
@@ -404,12 +398,15 @@ impl ServerError {
         let (status, code) = match self {
             ServerError::NotFound(_) => (StatusCode::NOT_FOUND, "NOT_FOUND"),
             ServerError::InvalidInput(_) => (StatusCode::BAD_REQUEST, "INVALID_INPUT"),
+            ServerError::Forbidden(_) => (StatusCode::FORBIDDEN, "FORBIDDEN"),
+            ServerError::ToolLimitExceeded(_) => (StatusCode::FORBIDDEN, "TOOL_LIMIT_EXCEEDED"),
             ServerError::ConfigError(_) => (StatusCode::BAD_REQUEST, "CONFIG_ERROR"),
             ServerError::NeuronError(_) => (StatusCode::INTERNAL_SERVER_ERROR, "NEURON_ERROR"),
             ServerError::RoutingError(_) => (StatusCode::BAD_GATEWAY, "ROUTING_ERROR"),
             ServerError::ClaudeError(_) => (StatusCode::BAD_GATEWAY, "CLAUDE_ERROR"),
             ServerError::IoError(_) => (StatusCode::INTERNAL_SERVER_ERROR, "IO_ERROR"),
             ServerError::SerializationError(_) => (StatusCode::BAD_REQUEST, "SERIALIZATION_ERROR"),
+            ServerError::Draining => (StatusCode::SERVICE_UNAVAILABLE, "DRAINING"),
             _ => (StatusCode::INTERNAL_SERVER_ERROR, "INTERNAL_ERROR"),
         };
         
@@ -432,6 +429,8 @@ impl ServerError {
         !matches!(self, 
             ServerError::NotFound(_) | 
             ServerError::InvalidInput(_) | 
+            ServerError::Forbidden(_) | 
+            ServerError::ToolLimitExceeded(_) |
             ServerError::ConfigError(_)
         )
     }
@@ -483,6 +482,27 @@ mod tests {
         assert_eq!(attempts, 3);
     }
     
+    #[tokio::test]
+    async fn test_tool_limits_are_not_retried() {
+        let retry = RetryMiddleware::new(3);
+        let mut attempts = 0;
+        let refused = ServerError::from_tool(hal9_core::Error::ToolLimitExceeded {
+            chain_id: "chain-1".to_string(),
+            limit: "invocations".to_string(),
+            message: "5 of 5 tool calls made".to_string(),
+        });
+        assert!(!refused.is_recoverable());
+        assert!(matches!(refused.recovery_strategy(), RecoveryStrategy::FailFast));
+        
+        let result: Result<(), _> = retry.execute(|| {
+            attempts += 1;
+            Err(ServerError::ToolLimitExceeded("Chain chain-1 exceeded its tool invocations limit".to_string()))
+        }).await;
+        
+        assert!(matches!(result, Err(ServerError::ToolLimitExceeded(_))));
+        assert_eq!(attempts, 1);
+    }
+    
     #[tokio::test]
     async fn test_error_store() {
         let store = ErrorStore::new(100);
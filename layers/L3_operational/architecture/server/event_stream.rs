@@ -0,0 +1,255 @@
+//! Signal stream over server-sent events
+//!
+//! Some proxies terminate WebSocket upgrades, so the events of `/api/v1/ws`
+//! are also served as `text/event-stream` on `/api/v1/signals/stream.sse`.
+//! Every event gets an increasing id and the last [`EVENT_BUFFER`] events are
+//! kept, so a client reconnecting with `Last-Event-ID` continues where it
+//! left off.
+//!
+//! SSE has no acknowledgements, so the server never waits for a client.
+//! Each connection keeps a cursor into the shared buffer rather than a queue
+//! of its own. A client falling more than [`EVENT_BUFFER`] events behind,
+//! while connected or between reconnects, is sent a `lagged` event with the
+//! number of events it missed and continues from the oldest one kept.
+
+use std::collections::VecDeque;
+use std::convert::Infallible;
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::{
+    extract::{Query, State},
+    http::HeaderMap,
+    response::sse::{Event, KeepAlive, Sse},
+};
+use futures::stream::{self, Stream};
+use hal9_api_types::{SignalEvent, StreamFilter, StreamLagged, LAGGED_EVENT};
+use parking_lot::Mutex;
+use tokio::sync::{broadcast, watch};
+
+use crate::{error::ServerError, server::HAL9Server};
+
+/// Events kept for resuming clients
+pub const EVENT_BUFFER: usize = 1024;
+
+/// Interval of heartbeat comments on an idle stream
+pub const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Capacity of the channel feeding WebSocket subscribers
+const BROADCAST_CAPACITY: usize = 1000;
+
+/// An event with its position in the stream
+#[derive(Debug, Clone)]
+pub struct StreamEvent {
+    pub id: u64,
+    pub event: SignalEvent,
+}
+
+/// Events kept after a cursor, see [`EventLog::read_after`]
+#[derive(Debug, Default)]
+pub struct Backlog {
+    /// Events after the cursor that are no longer kept
+    pub skipped: u64,
+    pub events: Vec<StreamEvent>,
+}
+
+struct Buffer {
+    events: VecDeque<StreamEvent>,
+    last_id: u64,
+}
+
+/// Numbers server events and fans them out to WebSocket subscribers and
+/// SSE readers
+pub struct EventLog {
+    broadcast: broadcast::Sender<SignalEvent>,
+    buffer: Mutex<Buffer>,
+    capacity: usize,
+    latest: watch::Sender<u64>,
+}
+
+impl Default for EventLog {
+    fn default() -> Self {
+        Self::new(EVENT_BUFFER)
+    }
+}
+
+impl EventLog {
+    pub fn new(capacity: usize) -> Self {
+        let (broadcast, _) = broadcast::channel(BROADCAST_CAPACITY);
+        let (latest, _) = watch::channel(0);
+        Self {
+            broadcast,
+            buffer: Mutex::new(Buffer {
+                events: VecDeque::with_capacity(capacity.max(1)),
+                last_id: 0,
+            }),
+            capacity: capacity.max(1),
+            latest,
+        }
+    }
+
+    /// Record and publish an event, returning its id
+    pub fn send(&self, event: SignalEvent) -> u64 {
+        let id = {
+            let mut buffer = self.buffer.lock();
+            buffer.last_id += 1;
+            let id = buffer.last_id;
+            buffer.events.push_back(StreamEvent { id, event: event.clone() });
+            while buffer.events.len() > self.capacity {
+                buffer.events.pop_front();
+            }
+            id
+        };
+        self.latest.send_replace(id);
+        let _ = self.broadcast.send(event);
+        id
+    }
+
+    /// Events published from now on
+    pub fn subscribe(&self) -> broadcast::Receiver<SignalEvent> {
+        self.broadcast.subscribe()
+    }
+
+    /// Id of the latest event, 0 before the first
+    pub fn last_id(&self) -> u64 {
+        self.buffer.lock().last_id
+    }
+
+    /// Changes whenever an event is recorded
+    pub fn watch(&self) -> watch::Receiver<u64> {
+        self.latest.subscribe()
+    }
+
+    /// Kept events with an id above `after`. A cursor ahead of the stream
+    /// comes from before a server restart and reads from the start.
+    pub fn read_after(&self, after: u64) -> Backlog {
+        let buffer = self.buffer.lock();
+        let after = if after > buffer.last_id { 0 } else { after };
+        let first_kept = buffer.events.front().map_or(buffer.last_id + 1, |event| event.id);
+        Backlog {
+            skipped: first_kept.saturating_sub(after + 1),
+            events: buffer.events.iter().filter(|event| event.id > after).cloned().collect(),
+        }
+    }
+}
+
+/// State of one SSE connection
+struct Reader {
+    events: Arc<EventLog>,
+    latest: watch::Receiver<u64>,
+    filter: StreamFilter,
+    cursor: u64,
+    pending: VecDeque<Event>,
+}
+
+impl Reader {
+    async fn next(mut self) -> Option<(Result<Event, Infallible>, Self)> {
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                return Some((Ok(event), self));
+            }
+            // Mark as seen before reading, so an event recorded meanwhile wakes us
+            self.latest.borrow_and_update();
+            let backlog = self.events.read_after(self.cursor);
+            if backlog.skipped > 0 {
+                self.pending.push_back(lagged_event(backlog.skipped));
+            }
+            for StreamEvent { id, event } in backlog.events {
+                self.cursor = id;
+                if self.filter.matches(&event) {
+                    self.pending.push_back(sse_event(id, &event));
+                }
+            }
+            if self.pending.is_empty() && self.latest.changed().await.is_err() {
+                return None;
+            }
+        }
+    }
+}
+
+fn sse_event(id: u64, event: &SignalEvent) -> Event {
+    Event::default()
+        .id(id.to_string())
+        .json_data(event)
+        .unwrap_or_else(|_| Event::default().id(id.to_string()))
+}
+
+fn lagged_event(skipped: u64) -> Event {
+    Event::default()
+        .event(LAGGED_EVENT)
+        .json_data(StreamLagged { skipped })
+        .unwrap_or_default()
+}
+
+/// `GET /api/v1/signals/stream.sse`: the signal stream as server-sent
+/// events. Without `Last-Event-ID` the stream starts with the next event.
+pub async fn signal_stream_sse(
+    State(server): State<Arc<HAL9Server>>,
+    headers: HeaderMap,
+    Query(filter): Query<StreamFilter>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, ServerError> {
+    let events = server.event_log();
+    let cursor = match headers.get("last-event-id") {
+        Some(value) => value
+            .to_str()
+            .ok()
+            .and_then(|value| value.trim().parse::<u64>().ok())
+            .ok_or_else(|| ServerError::InvalidInput("Last-Event-ID must be an event id".to_string()))?,
+        None => events.last_id(),
+    };
+
+    let reader = Reader {
+        latest: events.watch(),
+        events,
+        filter,
+        cursor,
+        pending: VecDeque::new(),
+    };
+    let stream = stream::unfold(reader, Reader::next);
+    Ok(Sse::new(stream).keep_alive(KeepAlive::new().interval(HEARTBEAT_INTERVAL).text("heartbeat")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn update(signal_id: &str) -> SignalEvent {
+        SignalEvent::SignalUpdate {
+            signal_id: signal_id.to_string(),
+            neuron_id: "n".to_string(),
+            status: "submitted".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_read_after_cursor() {
+        let log = EventLog::new(3);
+        for i in 1..=2 {
+            assert_eq!(log.send(update(&i.to_string())), i);
+        }
+        let backlog = log.read_after(1);
+        assert_eq!(backlog.skipped, 0);
+        assert_eq!(backlog.events.iter().map(|e| e.id).collect::<Vec<_>>(), vec![2]);
+        assert!(log.read_after(2).events.is_empty());
+    }
+
+    #[test]
+    fn test_evicted_events_are_reported_as_skipped() {
+        let log = EventLog::new(3);
+        for i in 1..=10 {
+            log.send(update(&i.to_string()));
+        }
+        let backlog = log.read_after(2);
+        assert_eq!(backlog.skipped, 5);
+        assert_eq!(backlog.events.iter().map(|e| e.id).collect::<Vec<_>>(), vec![8, 9, 10]);
+    }
+
+    #[test]
+    fn test_cursor_from_before_restart_reads_from_start() {
+        let log = EventLog::new(3);
+        log.send(update("1"));
+        let backlog = log.read_after(500);
+        assert_eq!(backlog.skipped, 0);
+        assert_eq!(backlog.events.len(), 1);
+    }
+}
@@ -0,0 +1,243 @@
+//! Fair scheduling of signal processing slots across users
+//!
+//! Neurons share a fixed number of processing slots (and through them the
+//! Claude connections). Under contention a plain FIFO queue lets one user who
+//! submitted many chains starve everyone else, so waiting requests are queued
+//! per user and freed slots are handed out round-robin across the users that
+//! currently have work waiting.
+
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use tokio::sync::oneshot;
+
+/// Fair round-robin scheduler for processing slots
+pub struct FairScheduler {
+    capacity: usize,
+    state: Mutex<SchedulerState>,
+}
+
+#[derive(Default)]
+struct SchedulerState {
+    in_use: usize,
+    /// Users with queued requests, in the order they will be served
+    ring: VecDeque<String>,
+    /// Queued requests per user
+    waiters: HashMap<String, VecDeque<oneshot::Sender<()>>>,
+    /// Slots currently held per user
+    active: HashMap<String, usize>,
+}
+
+/// Scheduler snapshot
+#[derive(Debug, Clone, Serialize)]
+pub struct SchedulerStats {
+    pub capacity: usize,
+    pub in_use: usize,
+    pub waiting: HashMap<String, usize>,
+    pub active: HashMap<String, usize>,
+}
+
+/// A held processing slot, released on drop
+pub struct SchedulerPermit {
+    scheduler: Arc<FairScheduler>,
+    user: String,
+}
+
+impl Drop for SchedulerPermit {
+    fn drop(&mut self) {
+        self.scheduler.release(&self.user);
+    }
+}
+
+/// A queued request; gives the slot back if dropped after being granted
+struct PendingGrant {
+    scheduler: Arc<FairScheduler>,
+    user: String,
+    rx: Option<oneshot::Receiver<()>>,
+}
+
+impl Drop for PendingGrant {
+    fn drop(&mut self) {
+        if let Some(mut rx) = self.rx.take() {
+            rx.close();
+            if rx.try_recv().is_ok() {
+                self.scheduler.release(&self.user);
+            }
+        }
+    }
+}
+
+impl FairScheduler {
+    /// Create a scheduler with `capacity` concurrent slots
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            state: Mutex::new(SchedulerState::default()),
+        }
+    }
+
+    /// Acquire a slot for `user`, waiting for its round-robin turn if all
+    /// slots are taken
+    pub async fn acquire(self: &Arc<Self>, user: &str) -> SchedulerPermit {
+        let rx = {
+            let mut state = self.state.lock();
+            if state.in_use < self.capacity && state.ring.is_empty() {
+                state.in_use += 1;
+                *state.active.entry(user.to_string()).or_insert(0) += 1;
+                return SchedulerPermit {
+                    scheduler: self.clone(),
+                    user: user.to_string(),
+                };
+            }
+
+            let (tx, rx) = oneshot::channel();
+            let queue = state.waiters.entry(user.to_string()).or_default();
+            queue.push_back(tx);
+            if queue.len() == 1 {
+                state.ring.push_back(user.to_string());
+            }
+            rx
+        };
+
+        let mut pending = PendingGrant {
+            scheduler: self.clone(),
+            user: user.to_string(),
+            rx: Some(rx),
+        };
+
+        if let Some(rx) = pending.rx.as_mut() {
+            // The sender is only dropped without sending if the scheduler
+            // itself is torn down; proceed either way.
+            let _ = rx.await;
+        }
+        pending.rx = None;
+
+        SchedulerPermit {
+            scheduler: self.clone(),
+            user: user.to_string(),
+        }
+    }
+
+    /// Current scheduler state
+    pub fn stats(&self) -> SchedulerStats {
+        let state = self.state.lock();
+        SchedulerStats {
+            capacity: self.capacity,
+            in_use: state.in_use,
+            waiting: state.waiters.iter()
+                .map(|(user, queue)| (user.clone(), queue.len()))
+                .collect(),
+            active: state.active.clone(),
+        }
+    }
+
+    /// Slots currently held by `user`
+    pub fn active_for(&self, user: &str) -> usize {
+        self.state.lock().active.get(user).copied().unwrap_or(0)
+    }
+
+    fn release(&self, user: &str) {
+        let mut state = self.state.lock();
+        state.in_use = state.in_use.saturating_sub(1);
+        if let Some(count) = state.active.get_mut(user) {
+            *count = count.saturating_sub(1);
+            if *count == 0 {
+                state.active.remove(user);
+            }
+        }
+        Self::dispatch(&mut state, self.capacity);
+    }
+
+    /// Hand free slots to waiting users, one request per user per turn
+    fn dispatch(state: &mut SchedulerState, capacity: usize) {
+        while state.in_use < capacity {
+            let Some(user) = state.ring.pop_front() else {
+                break;
+            };
+
+            let (waiter, more_waiting) = match state.waiters.get_mut(&user) {
+                Some(queue) => (queue.pop_front(), !queue.is_empty()),
+                None => (None, false),
+            };
+            if more_waiting {
+                state.ring.push_back(user.clone());
+            } else {
+                state.waiters.remove(&user);
+            }
+
+            if let Some(waiter) = waiter {
+                // A closed receiver means the request was abandoned
+                if waiter.send(()).is_ok() {
+                    state.in_use += 1;
+                    *state.active.entry(user).or_insert(0) += 1;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_uncontended_acquire_is_immediate() {
+        let scheduler = Arc::new(FairScheduler::new(2));
+        let a = scheduler.acquire("alice").await;
+        let b = scheduler.acquire("bob").await;
+        assert_eq!(scheduler.stats().in_use, 2);
+        drop(a);
+        drop(b);
+        assert_eq!(scheduler.stats().in_use, 0);
+    }
+
+    #[tokio::test]
+    async fn test_slots_granted_round_robin() {
+        let scheduler = Arc::new(FairScheduler::new(1));
+        let held = scheduler.acquire("heavy").await;
+
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let mut handles = Vec::new();
+        for user in ["heavy", "heavy", "heavy", "light"] {
+            let scheduler = scheduler.clone();
+            let order = order.clone();
+            handles.push(tokio::spawn(async move {
+                let _permit = scheduler.acquire(user).await;
+                order.lock().push(user);
+            }));
+            // Ensure queueing order
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+
+        drop(held);
+        for handle in handles {
+            handle.await.unwrap();
+        }
+
+        // light is served right after the first heavy request, not last
+        assert_eq!(*order.lock(), vec!["heavy", "light", "heavy", "heavy"]);
+    }
+
+    #[tokio::test]
+    async fn test_abandoned_request_does_not_leak_slot() {
+        let scheduler = Arc::new(FairScheduler::new(1));
+        let held = scheduler.acquire("a").await;
+
+        let waiting = {
+            let scheduler = scheduler.clone();
+            tokio::spawn(async move {
+                let _permit = scheduler.acquire("b").await;
+            })
+        };
+        tokio::time::sleep(Duration::from_millis(5)).await;
+        waiting.abort();
+        let _ = waiting.await;
+
+        drop(held);
+        assert_eq!(scheduler.stats().in_use, 0);
+        let _again = scheduler.acquire("c").await;
+        assert_eq!(scheduler.stats().in_use, 1);
+    }
+}
@@ -0,0 +1,178 @@
+//! Fan-out limits of chains
+//!
+//! A response forwarding to more neurons than its layer's entry in
+//! `fan_out.max_children` keeps only the first ones. A chain may also
+//! process at most `fan_out.node_budget` signals. The budget travels with
+//! the signals under `fan_out.budget`: each signal carries what its subtree
+//! may still spend, itself included, and splits the rest between the
+//! children it keeps. No chain can exceed the budget however deep it goes,
+//! without any shared counter.
+//!
+//! Dropped branches are logged and recorded on the chain's report. With
+//! `fan_out.strict` a response over a limit fails the chain instead, and
+//! none of its branches are kept.
+
+use std::collections::HashMap;
+
+use tracing::warn;
+
+use hal9_core::{
+    config::FanOutConfig, metadata_schema::keys, Error, NeuronSignal, PropagationType, Result,
+};
+
+pub use hal9_api_types::{FanOutTruncation, FanOutUsage, TruncationReason};
+
+/// Metadata key of what the subtree of a signal may still spend
+pub const FAN_OUT_BUDGET_KEY: &str = keys::fan_out::BUDGET;
+
+/// Budget of the subtree rooted at `metadata`'s signal, if one applies
+pub fn budget_of(metadata: &HashMap<String, String>) -> Option<u64> {
+    metadata.get(FAN_OUT_BUDGET_KEY).and_then(|budget| budget.parse().ok())
+}
+
+/// Leave a feedback signal no budget to forward with. Backward signals are
+/// not counted against the budget, so they may not spawn branches that are.
+pub fn mark_feedback(metadata: &mut HashMap<String, String>) {
+    if metadata.contains_key(FAN_OUT_BUDGET_KEY) {
+        metadata.insert(FAN_OUT_BUDGET_KEY.to_string(), "0".to_string());
+    }
+}
+
+/// Applies the configured fan-out limits to responses
+#[derive(Debug, Clone, Default)]
+pub struct FanOutPolicy {
+    config: FanOutConfig,
+}
+
+impl FanOutPolicy {
+    pub fn new(config: FanOutConfig) -> Self {
+        Self { config }
+    }
+
+    /// Give a chain's root signal the node budget. A submitter may ask for
+    /// a smaller one, not a larger one.
+    pub fn start(&self, root: &mut NeuronSignal) {
+        if let Some(budget) = self.config.node_budget {
+            let budget = budget_of(&root.metadata).map_or(budget, |asked| asked.min(budget));
+            root.metadata.insert(FAN_OUT_BUDGET_KEY.to_string(), budget.to_string());
+        }
+    }
+
+    /// Drop the forward signals in `children` over the limits of a response
+    /// by a neuron on `layer` to `parent`, and split the rest of the
+    /// parent's budget between those kept. Returns what was dropped, if
+    /// anything was.
+    pub fn apply(
+        &self,
+        parent: &NeuronSignal,
+        layer: &str,
+        children: &mut Vec<NeuronSignal>,
+    ) -> Option<FanOutTruncation> {
+        let requested = children.iter().filter(|s| s.propagation_type == PropagationType::Forward).count();
+        let budget = budget_of(&parent.metadata).or(self.config.node_budget);
+        let max_children = self.config.max_children_for(layer).map(|max| max as usize);
+
+        // The parent spends one node of its budget on itself
+        let remaining = budget.map(|budget| budget.saturating_sub(1) as usize);
+        let mut allowed = requested;
+        let mut reason = None;
+        if let Some(max) = max_children.filter(|max| allowed > *max) {
+            allowed = max;
+            reason = Some(TruncationReason::MaxChildren);
+        }
+        if let Some(remaining) = remaining.filter(|remaining| allowed > *remaining) {
+            allowed = remaining;
+            reason = Some(TruncationReason::NodeBudget);
+        }
+
+        let truncation = reason.map(|reason| FanOutTruncation {
+            signal_id: parent.signal_id.to_string(),
+            neuron_id: parent.to_neuron.clone(),
+            layer: layer.to_string(),
+            requested,
+            allowed,
+            reason,
+            failed: self.config.strict,
+        });
+        if let Some(truncation) = &truncation {
+            warn!(
+                target: "neuron.fan_out",
+                neuron_id = %truncation.neuron_id,
+                signal_id = %truncation.signal_id,
+                requested,
+                allowed,
+                strict = self.config.strict,
+                "Response forwards to {} neurons, {} allowed", requested, allowed
+            );
+            if self.config.strict {
+                allowed = 0;
+            }
+        }
+
+        let mut kept = 0;
+        children.retain(|signal| {
+            if signal.propagation_type != PropagationType::Forward {
+                return true;
+            }
+            kept += 1;
+            kept <= allowed
+        });
+
+        if let Some(remaining) = remaining {
+            let mut shares = split(remaining.saturating_sub(allowed), allowed).into_iter();
+            for child in children.iter_mut() {
+                match child.propagation_type {
+                    PropagationType::Forward => {
+                        let share = shares.next().unwrap_or(0) + 1;
+                        child.metadata.insert(FAN_OUT_BUDGET_KEY.to_string(), share.to_string());
+                    }
+                    PropagationType::Backward => {
+                        child.metadata.insert(FAN_OUT_BUDGET_KEY.to_string(), "0".to_string());
+                    }
+                }
+            }
+        }
+
+        truncation
+    }
+}
+
+/// Why a response's branches were dropped, or its chain failed
+pub fn describe(truncation: &FanOutTruncation) -> String {
+    let limit = match truncation.reason {
+        TruncationReason::MaxChildren => "the layer's maximum children",
+        TruncationReason::NodeBudget => "the chain's remaining node budget",
+    };
+    format!(
+        "Fan-out of {} branches exceeds {} ({})",
+        truncation.requested, limit, truncation.allowed
+    )
+}
+
+/// `total` split into `parts` shares differing by at most one
+fn split(total: usize, parts: usize) -> Vec<usize> {
+    if parts == 0 {
+        return Vec::new();
+    }
+    (0..parts).map(|i| total / parts + usize::from(i < total % parts)).collect()
+}
+
+/// Check that limits, when set, leave room for a chain's root
+pub fn validate(config: &FanOutConfig) -> Result<()> {
+    if config.node_budget == Some(0) {
+        return Err(Error::Config("Invalid fan_out: node_budget must be positive".to_string()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_shares_differ_by_at_most_one() {
+        assert_eq!(split(7, 3), vec![3, 2, 2]);
+        assert_eq!(split(2, 4), vec![1, 1, 0, 0]);
+        assert!(split(5, 0).is_empty());
+    }
+}
@@ -2,22 +2,23 @@
 //! HAL9 Collective Intelligence vs Individual AI Models
 
 use axum::{
-    extract::{ws::WebSocket, WebSocketUpgrade, State, Path},
+    extract::{ws::{close_code, CloseFrame, Message, WebSocket}, WebSocketUpgrade, State, Path},
     response::{Html, IntoResponse},
     routing::{get, post},
     Json, Router,
 };
 use futures::{sink::SinkExt, stream::StreamExt};
 use serde::{Deserialize, Serialize};
+use dashmap::DashMap;
 use std::{
-    collections::HashMap,
-    sync::Arc,
+    collections::{HashMap, VecDeque},
+    sync::{atomic::{AtomicU64, Ordering}, Arc},
     time::Duration,
 };
-use tokio::sync::{broadcast, RwLock, Mutex};
+use tokio::sync::{Notify, RwLock, Mutex};
 use tower_http::cors::CorsLayer;
 use uuid::Uuid;
-use tracing::info;
+use tracing::{info, warn};
 
 // Game Constants
 const BOARD_SIZE: usize = 19;
@@ -26,6 +27,12 @@ const CONSCIOUSNESS_THRESHOLD: f32 = 0.8;
 const MAX_NEURONS_PER_PLAYER: usize = 50;
 const SIMULATION_TICK_MS: u64 = 100;
 
+/// Unsent messages a connection may fall behind by before its pending state
+/// updates are replaced by a snapshot
+pub const OUTBOUND_LAG_LIMIT: usize = 32;
+/// Unsent messages left after coalescing at which a connection is closed
+pub const OUTBOUND_QUEUE_LIMIT: usize = 256;
+
 /// Main game types
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type")]
@@ -215,10 +222,225 @@ pub enum ServerMessage {
     Error { message: String },
 }
 
+impl ServerMessage {
+    /// Whether the message only updates game state, so a snapshot of the
+    /// game taken after it carries everything it did
+    fn is_state_update(&self) -> bool {
+        matches!(
+            self,
+            ServerMessage::GameState(_)
+                | ServerMessage::PlayerJoined { .. }
+                | ServerMessage::NeuronPlaced { .. }
+                | ServerMessage::ConsciousnessUpdate { .. }
+        )
+    }
+}
+
+/// A message as written to the socket
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutboundMessage {
+    #[serde(flatten)]
+    pub message: ServerMessage,
+    /// The client fell behind and the state updates it missed were replaced
+    /// by this snapshot; render the game from it rather than apply it
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub lagged: bool,
+}
+
+/// Messages waiting to be written to one connection.
+///
+/// A client that reads slower than the game changes falls behind. Once more
+/// than the lag limit of messages are waiting, the state updates among them
+/// are replaced by a single snapshot of the game, flagged `lagged` and sent
+/// first; errors, events and game over messages keep their order after it.
+/// A client still that far behind past the queue limit is disconnected.
+pub struct Outbound {
+    id: String,
+    lag_limit: usize,
+    queue_limit: usize,
+    queue: parking_lot::Mutex<OutboundQueue>,
+    notify: Notify,
+    totals: Arc<OutboundTotals>,
+}
+
+#[derive(Default)]
+struct OutboundQueue {
+    messages: VecDeque<OutboundMessage>,
+    sent: u64,
+    coalesced: u64,
+    closed: bool,
+}
+
+#[derive(Default)]
+struct OutboundTotals {
+    coalesced: AtomicU64,
+    lag_disconnects: AtomicU64,
+}
+
+impl Outbound {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Queue `message` for the client; `game` is the state of the game after
+    /// it, snapshotted if the client is too far behind. False once the
+    /// connection is closed.
+    pub fn send(&self, message: ServerMessage, game: &GameState) -> bool {
+        let mut queue = self.queue.lock();
+        if queue.closed {
+            return false;
+        }
+        queue.messages.push_back(OutboundMessage { message, lagged: false });
+        if queue.messages.len() <= self.lag_limit {
+            drop(queue);
+            self.notify.notify_one();
+            return true;
+        }
+
+        let pending = queue.messages.len();
+        queue.messages.retain(|pending| !pending.message.is_state_update());
+        if queue.messages.len() < pending {
+            queue.messages.push_front(OutboundMessage {
+                message: ServerMessage::GameState(game.clone()),
+                lagged: true,
+            });
+            queue.coalesced += 1;
+            self.totals.coalesced.fetch_add(1, Ordering::Relaxed);
+        }
+        if queue.messages.len() > self.queue_limit {
+            warn!("Closing genius game connection {}: {} messages behind", self.id, queue.messages.len());
+            queue.closed = true;
+            queue.messages.clear();
+            self.totals.lag_disconnects.fetch_add(1, Ordering::Relaxed);
+        }
+        let open = !queue.closed;
+        drop(queue);
+        self.notify.notify_one();
+        open
+    }
+
+    /// Next message to write, waiting for one; `None` once closed
+    pub async fn recv(&self) -> Option<OutboundMessage> {
+        loop {
+            let notified = self.notify.notified();
+            {
+                let mut queue = self.queue.lock();
+                if queue.closed {
+                    return None;
+                }
+                if let Some(message) = queue.messages.pop_front() {
+                    queue.sent += 1;
+                    return Some(message);
+                }
+            }
+            notified.await;
+        }
+    }
+
+    /// Whether the connection was closed for falling behind
+    pub fn is_closed(&self) -> bool {
+        self.queue.lock().closed
+    }
+
+    pub fn stats(&self) -> OutboundStats {
+        let queue = self.queue.lock();
+        OutboundStats {
+            connection_id: self.id.clone(),
+            queue_depth: queue.messages.len(),
+            sent: queue.sent,
+            coalesced: queue.coalesced,
+        }
+    }
+}
+
+/// Send counters of one connection
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct OutboundStats {
+    pub connection_id: String,
+    /// Messages waiting to be written
+    pub queue_depth: usize,
+    pub sent: u64,
+    /// Times its pending state updates were replaced by a snapshot
+    pub coalesced: u64,
+}
+
+/// Outbound queues of the open game connections
+pub struct GameConnections {
+    outbounds: DashMap<String, Arc<Outbound>>,
+    totals: Arc<OutboundTotals>,
+    lag_limit: usize,
+    queue_limit: usize,
+}
+
+impl Default for GameConnections {
+    fn default() -> Self {
+        Self::new(OUTBOUND_LAG_LIMIT, OUTBOUND_QUEUE_LIMIT)
+    }
+}
+
+impl GameConnections {
+    pub fn new(lag_limit: usize, queue_limit: usize) -> Self {
+        Self {
+            outbounds: DashMap::new(),
+            totals: Arc::new(OutboundTotals::default()),
+            lag_limit,
+            queue_limit: queue_limit.max(lag_limit),
+        }
+    }
+
+    /// Queue for a new connection
+    pub fn open(&self) -> Arc<Outbound> {
+        let outbound = Arc::new(Outbound {
+            id: Uuid::new_v4().to_string(),
+            lag_limit: self.lag_limit,
+            queue_limit: self.queue_limit,
+            queue: parking_lot::Mutex::new(OutboundQueue::default()),
+            notify: Notify::new(),
+            totals: self.totals.clone(),
+        });
+        self.outbounds.insert(outbound.id.clone(), outbound.clone());
+        outbound
+    }
+
+    pub fn close(&self, id: &str) {
+        self.outbounds.remove(id);
+    }
+
+    pub fn len(&self) -> usize {
+        self.outbounds.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.outbounds.is_empty()
+    }
+
+    pub fn stats(&self) -> GameConnectionStats {
+        let mut connections: Vec<OutboundStats> = self.outbounds.iter().map(|entry| entry.value().stats()).collect();
+        connections.sort_by(|a, b| a.connection_id.cmp(&b.connection_id));
+        GameConnectionStats {
+            connections,
+            coalesced: self.totals.coalesced.load(Ordering::Relaxed),
+            lag_disconnects: self.totals.lag_disconnects.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Send counters of the game connections
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct GameConnectionStats {
+    /// Open connections
+    pub connections: Vec<OutboundStats>,
+    /// Snapshots sent in place of pending state updates, closed connections
+    /// included
+    pub coalesced: u64,
+    /// Connections closed for falling too far behind
+    pub lag_disconnects: u64,
+}
+
 /// Application state
 pub struct AppState {
     pub games: HashMap<String, Arc<Mutex<GameState>>>,
-    pub connections: HashMap<String, broadcast::Sender<ServerMessage>>,
+    pub connections: Arc<GameConnections>,
 }
 
 type SharedState = Arc<RwLock<AppState>>;
@@ -248,21 +470,26 @@ async fn genius_websocket_handler(
 
 async fn handle_genius_websocket(socket: WebSocket, state: SharedState) {
     let (mut sender, mut receiver) = socket.split();
-    let (tx, mut rx) = broadcast::channel(100);
     
     // Store connection
-    let conn_id = Uuid::new_v4().to_string();
-    state.write().await.connections.insert(conn_id.clone(), tx.clone());
+    let connections = state.read().await.connections.clone();
+    let tx = connections.open();
     
-    // Spawn task to forward broadcast messages
+    // Spawn task to write queued messages
+    let outbound = tx.clone();
     let mut send_task = tokio::spawn(async move {
-        while let Ok(msg) = rx.recv().await {
-            if sender.send(axum::extract::ws::Message::Text(
+        while let Some(msg) = outbound.recv().await {
+            if sender.send(Message::Text(
                 serde_json::to_string(&msg).unwrap()
             )).await.is_err() {
-                break;
+                return;
             }
         }
+        // Closed for falling behind; the client reconnects for a fresh state
+        let _ = sender.send(Message::Close(Some(CloseFrame {
+            code: close_code::AGAIN,
+            reason: "lagged".into(),
+        }))).await;
     });
     
     // Handle incoming messages
@@ -270,7 +497,7 @@ async fn handle_genius_websocket(socket: WebSocket, state: SharedState) {
     let tx_clone = tx.clone();
     let mut recv_task = tokio::spawn(async move {
         while let Some(Ok(msg)) = receiver.next().await {
-            if let axum::extract::ws::Message::Text(text) = msg {
+            if let Message::Text(text) = msg {
                 if let Ok(client_msg) = serde_json::from_str::<ClientMessage>(&text) {
                     handle_client_message(client_msg, &state_clone, &tx_clone).await;
                 }
@@ -285,13 +512,13 @@ async fn handle_genius_websocket(socket: WebSocket, state: SharedState) {
     }
     
     // Clean up connection
-    state.write().await.connections.remove(&conn_id);
+    connections.close(tx.id());
 }
 
 async fn handle_client_message(
     msg: ClientMessage,
     state: &SharedState,
-    tx: &broadcast::Sender<ServerMessage>,
+    tx: &Arc<Outbound>,
 ) {
     match msg {
         ClientMessage::JoinGame { player_id, player_type } => {
@@ -326,8 +553,8 @@ async fn handle_client_message(
                 let mut game = game_mutex.lock().await;
                 game.players.insert(player.id.clone(), player.clone());
                 
-                let _ = tx.send(ServerMessage::PlayerJoined { player });
-                let _ = tx.send(ServerMessage::GameState(game.clone()));
+                tx.send(ServerMessage::PlayerJoined { player }, &game);
+                tx.send(ServerMessage::GameState(game.clone()), &game);
             }
         }
         
@@ -350,7 +577,7 @@ async fn place_neuron(
     y: usize,
     neuron_type: NeuronType,
     state: &SharedState,
-    tx: &broadcast::Sender<ServerMessage>,
+    tx: &Outbound,
 ) {
     // Find active game
     let active_game = {
@@ -368,16 +595,16 @@ async fn place_neuron(
         
         // Check if position is valid
         if x >= BOARD_SIZE || y >= BOARD_SIZE {
-            let _ = tx.send(ServerMessage::Error { 
+            tx.send(ServerMessage::Error { 
                 message: "Invalid position".to_string() 
-            });
+            }, &game);
             return;
         }
         
         if game.board.grid[y][x].is_some() {
-            let _ = tx.send(ServerMessage::Error { 
+            tx.send(ServerMessage::Error { 
                 message: "Position already occupied".to_string() 
-            });
+            }, &game);
             return;
         }
         
@@ -430,27 +657,29 @@ async fn place_neuron(
         game.consciousness_level = (game.consciousness_level + consciousness_delta).min(1.0);
         
         // Send updates
-        let _ = tx.send(ServerMessage::NeuronPlaced { 
+        tx.send(ServerMessage::NeuronPlaced { 
             neuron, 
             consciousness_delta 
-        });
+        }, &game);
         
-        let _ = tx.send(ServerMessage::ConsciousnessUpdate {
+        // Kept on the board so a snapshot carries them too
+        game.board.emergence_patterns = detect_patterns(&game.board);
+        tx.send(ServerMessage::ConsciousnessUpdate {
             level: game.consciousness_level,
-            patterns: detect_patterns(&game.board),
-        });
+            patterns: game.board.emergence_patterns.clone(),
+        }, &game);
         
         // Check win condition
         if game.consciousness_level >= CONSCIOUSNESS_THRESHOLD {
             game.status = GameStatus::Finished;
             game.winner = Some("HAL9 Collective".to_string());
             
-            let _ = tx.send(ServerMessage::GameOver {
+            tx.send(ServerMessage::GameOver {
                 winner: game.winner.clone().unwrap(),
                 final_scores: game.players.iter()
                     .map(|(id, p)| (id.clone(), p.score))
                     .collect(),
-            });
+            }, &game);
         }
     }
 }
@@ -516,7 +745,7 @@ fn detect_patterns(board: &Board) -> Vec<EmergencePattern> {
 
 async fn start_game_simulation(
     state: &SharedState,
-    tx: &broadcast::Sender<ServerMessage>,
+    tx: &Arc<Outbound>,
 ) {
     info!("Starting game simulation");
     
@@ -538,7 +767,7 @@ async fn start_game_simulation(
             game.status = GameStatus::Running;
             game.started_at = Some(chrono::Utc::now());
             
-            let _ = tx.send(ServerMessage::GameState(game.clone()));
+            tx.send(ServerMessage::GameState(game.clone()), &game);
         }
         
         // Spawn simulation task
@@ -565,12 +794,13 @@ async fn start_game_simulation(
                     let patterns = detect_patterns(&game.board);
                     let consciousness_boost = patterns.len() as f32 * 0.001;
                     game.consciousness_level = (game.consciousness_level + consciousness_boost).min(1.0);
+                    game.board.emergence_patterns = patterns.clone();
                     
                     // Send updates
-                    let _ = tx_clone.send(ServerMessage::ConsciousnessUpdate {
+                    tx_clone.send(ServerMessage::ConsciousnessUpdate {
                         level: game.consciousness_level,
-                        patterns: patterns.clone(),
-                    });
+                        patterns,
+                    }, &game);
                     
                     // Update round
                     game.round += 1;
@@ -585,12 +815,12 @@ async fn start_game_simulation(
                         
                         game.winner = Some(winner.to_string());
                         
-                        let _ = tx_clone.send(ServerMessage::GameOver {
+                        tx_clone.send(ServerMessage::GameOver {
                             winner: winner.to_string(),
                             final_scores: game.players.iter()
                                 .map(|(id, p)| (id.clone(), p.score))
                                 .collect(),
-                        });
+                        }, &game);
                         
                         break;
                     }
@@ -0,0 +1,552 @@
+//! Goals submitted through the API and the chains working towards them
+//!
+//! The intelligence coordinator decomposes a goal into tasks, each submitted
+//! as the root signal of its own chain. As chains finish, their token usage is
+//! priced against the goal's resource constraint and, when an evaluator neuron
+//! is configured, the results are scored against the success criteria. A
+//! violated constraint pauses the goal: chains already running finish, but no
+//! further tasks are submitted.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use serde::Serialize;
+use tokio::sync::broadcast;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+use hal9_core::config::GoalsConfig;
+use hal9_core::hierarchical::intelligence::{
+    evaluation_prompt, parse_evaluation, ConstraintType, Goal, GoalPlan, GoalTask, TaskRelease,
+};
+use hal9_core::{metadata_schema::keys, NeuronConfig, NeuronSignal};
+
+use crate::chain_limits::ChainOwner;
+use crate::chain_tracker::{ChainResult, ChainStatus, ChainTracker};
+use crate::error::{ServerError, ServerResult};
+
+/// Metadata key tagging a goal's root signals with the goal ID
+pub const GOAL_ID_KEY: &str = keys::goal::ID;
+
+/// Evaluation answer key checked against a goal's quality constraint
+pub const QUALITY_KEY: &str = "quality";
+
+/// Starts chains on behalf of goals
+#[async_trait]
+pub trait GoalExecutor: Send + Sync {
+    /// Submit `signal` as the root of a new chain, returning the chain ID
+    async fn submit(&self, owner: Option<&ChainOwner>, signal: NeuronSignal) -> ServerResult<String>;
+}
+
+/// Goal lifecycle state
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GoalState {
+    /// Chains are running or tasks remain
+    Active,
+    /// A constraint was violated; no further tasks are submitted
+    Paused,
+    /// Every success criterion is met
+    Achieved,
+    /// All tasks finished without meeting every criterion
+    Completed,
+    /// Every task chain failed
+    Failed,
+}
+
+/// Why a goal started a chain
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GoalChainKind {
+    Task,
+    Evaluation,
+}
+
+/// A chain spawned by a goal
+#[derive(Debug, Clone, Serialize)]
+pub struct GoalChain {
+    pub chain_id: String,
+    pub kind: GoalChainKind,
+    pub neuron_id: String,
+    pub status: ChainStatus,
+    pub cost_usd: f64,
+}
+
+/// Latest evaluation of one success criterion
+#[derive(Debug, Clone, Serialize)]
+pub struct CriterionStatus {
+    pub name: String,
+    pub target_value: f32,
+    /// Score from the evaluator, if it has answered yet
+    pub value: Option<f32>,
+    pub met: bool,
+}
+
+/// A goal's progress as served by the API
+#[derive(Debug, Clone, Serialize)]
+pub struct GoalStatus {
+    pub goal: Goal,
+    pub state: GoalState,
+    /// Share of tasks finished and criteria met, from 0.0 to 1.0
+    pub progress: f32,
+    pub chains: Vec<GoalChain>,
+    /// Tasks not yet submitted
+    pub pending_tasks: usize,
+    /// Attempts made, including retries of adaptive goals
+    pub rounds: u32,
+    pub cost_usd: f64,
+    /// Budget from the goal's resource constraint
+    pub max_cost_usd: Option<f32>,
+    pub criteria: Vec<CriterionStatus>,
+    /// Constraints found violated, in the order they were detected
+    pub violations: Vec<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+struct GoalRecord {
+    goal: Goal,
+    plan: GoalPlan,
+    owner: Option<ChainOwner>,
+    state: GoalState,
+    chains: Vec<(String, GoalChainKind, String)>,
+    pending: VecDeque<GoalTask>,
+    rounds: u32,
+    scores: HashMap<String, f32>,
+    violations: Vec<String>,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+}
+
+impl GoalRecord {
+    fn unmet_criteria(&self) -> Vec<&hal9_core::hierarchical::intelligence::Criterion> {
+        self.goal
+            .success_criteria
+            .iter()
+            .filter(|criterion| !self.scores.get(&criterion.name).is_some_and(|value| criterion.is_met(*value)))
+            .collect()
+    }
+
+    fn achieved(&self) -> bool {
+        !self.goal.success_criteria.is_empty() && self.unmet_criteria().is_empty()
+    }
+
+    fn pause(&mut self, violation: String) {
+        if !self.violations.contains(&violation) {
+            warn!("Goal {} paused: {}", self.goal.id, violation);
+            self.violations.push(violation);
+        }
+        if self.state == GoalState::Active {
+            self.state = GoalState::Paused;
+        }
+    }
+}
+
+/// Tracks goals and drives them forward as their chains finish
+pub struct GoalManager {
+    config: GoalsConfig,
+    /// Neuron receiving root signals for each layer
+    entry_neurons: Vec<(String, String)>,
+    /// Dollars per 1k prompt and completion tokens
+    pricing: (f64, f64),
+    chain_tracker: Arc<ChainTracker>,
+    executor: Arc<dyn GoalExecutor>,
+    goals: DashMap<Uuid, GoalRecord>,
+    /// Goal each spawned chain belongs to
+    chains: DashMap<String, Uuid>,
+}
+
+impl GoalManager {
+    /// Create a goal manager submitting tasks to the first configured neuron
+    /// of each layer
+    pub fn new(
+        config: GoalsConfig,
+        neurons: &[NeuronConfig],
+        pricing: (f64, f64),
+        chain_tracker: Arc<ChainTracker>,
+        executor: Arc<dyn GoalExecutor>,
+    ) -> Self {
+        let mut entry_neurons: Vec<(String, String)> = Vec::new();
+        for neuron in neurons {
+            if !entry_neurons.iter().any(|(layer, _)| *layer == neuron.layer) {
+                entry_neurons.push((neuron.layer.clone(), neuron.id.clone()));
+            }
+        }
+
+        Self {
+            config,
+            entry_neurons,
+            pricing,
+            chain_tracker,
+            executor,
+            goals: DashMap::new(),
+            chains: DashMap::new(),
+        }
+    }
+
+    /// Follow finished chains in the background
+    pub fn start(self: &Arc<Self>) {
+        let mut finished = self.chain_tracker.subscribe();
+        let manager = self.clone();
+
+        tokio::spawn(async move {
+            loop {
+                match finished.recv().await {
+                    Ok(result) => manager.on_chain_finished(&result).await,
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        error!("Goal driver lagged, {} chain events missed", skipped);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        });
+    }
+
+    /// Register a goal and submit the tasks its plan releases first
+    pub async fn create(&self, goal: Goal, plan: GoalPlan, owner: Option<ChainOwner>) -> ServerResult<GoalStatus> {
+        if goal.description.trim().is_empty() {
+            return Err(ServerError::InvalidInput("Goal description must not be empty".to_string()));
+        }
+        if self.goals.contains_key(&goal.id) {
+            return Err(ServerError::InvalidInput(format!("Goal {} already exists", goal.id)));
+        }
+        for task in &plan.tasks {
+            self.entry_neuron(&task.layer)?;
+        }
+
+        let goal_id = goal.id;
+        let now = Utc::now();
+        let mut record = GoalRecord {
+            pending: plan.tasks.iter().cloned().collect(),
+            goal,
+            plan,
+            owner,
+            state: GoalState::Active,
+            chains: Vec::new(),
+            rounds: 1,
+            scores: HashMap::new(),
+            violations: Vec::new(),
+            created_at: now,
+            updated_at: now,
+        };
+        self.check_constraints(&mut record);
+        info!("Goal {} created with {} tasks", goal_id, record.pending.len());
+        self.goals.insert(goal_id, record);
+
+        self.release(goal_id).await;
+        self.status(goal_id)
+            .ok_or_else(|| ServerError::Internal(format!("Goal {} vanished", goal_id)))
+    }
+
+    /// Get a goal's progress
+    pub fn status(&self, goal_id: Uuid) -> Option<GoalStatus> {
+        let record = self.goals.get(&goal_id)?;
+
+        let chains: Vec<GoalChain> = record
+            .chains
+            .iter()
+            .map(|(chain_id, kind, neuron_id)| {
+                let result = self.chain_tracker.aggregate(chain_id);
+                GoalChain {
+                    chain_id: chain_id.clone(),
+                    kind: *kind,
+                    neuron_id: neuron_id.clone(),
+                    status: result.as_ref().map(|r| r.status).unwrap_or(ChainStatus::Cancelled),
+                    cost_usd: result.as_ref().map(|r| self.price(r)).unwrap_or(0.0),
+                }
+            })
+            .collect();
+
+        let criteria: Vec<CriterionStatus> = record
+            .goal
+            .success_criteria
+            .iter()
+            .map(|criterion| {
+                let value = record.scores.get(&criterion.name).copied();
+                CriterionStatus {
+                    name: criterion.name.clone(),
+                    target_value: criterion.target_value,
+                    value,
+                    met: value.is_some_and(|value| criterion.is_met(value)),
+                }
+            })
+            .collect();
+
+        let tasks: Vec<_> = chains.iter().filter(|chain| chain.kind == GoalChainKind::Task).collect();
+        let finished = tasks.iter().filter(|chain| chain.status != ChainStatus::Running).count();
+        let met = criteria.iter().filter(|criterion| criterion.met).count();
+        let total = tasks.len() + record.pending.len() + criteria.len();
+        let progress = if record.state == GoalState::Achieved || total == 0 {
+            1.0
+        } else {
+            (finished + met) as f32 / total as f32
+        };
+
+        Some(GoalStatus {
+            goal: record.goal.clone(),
+            state: record.state,
+            progress,
+            cost_usd: chains.iter().map(|chain| chain.cost_usd).sum(),
+            chains,
+            pending_tasks: record.pending.len(),
+            rounds: record.rounds,
+            max_cost_usd: max_cost(&record.goal),
+            criteria,
+            violations: record.violations.clone(),
+            created_at: record.created_at,
+            updated_at: record.updated_at,
+        })
+    }
+
+    /// Advance the goal a finished chain belongs to
+    pub async fn on_chain_finished(&self, result: &ChainResult) {
+        let Some(goal_id) = self.chains.get(&result.chain_id).map(|entry| *entry) else {
+            return;
+        };
+
+        let evaluate = {
+            let Some(mut record) = self.goals.get_mut(&goal_id) else {
+                return;
+            };
+            record.updated_at = Utc::now();
+
+            let kind = record
+                .chains
+                .iter()
+                .find(|(chain_id, _, _)| *chain_id == result.chain_id)
+                .map(|(_, kind, _)| *kind);
+            if kind == Some(GoalChainKind::Evaluation) {
+                if let Some(answer) = &result.final_output {
+                    record.scores.extend(parse_evaluation(answer));
+                }
+            }
+            if record.achieved() {
+                record.state = GoalState::Achieved;
+            }
+            self.check_constraints(&mut record);
+
+            kind == Some(GoalChainKind::Task)
+                && record.state == GoalState::Active
+                && !record.goal.success_criteria.is_empty()
+                && self.config.evaluator.is_some()
+        };
+
+        if evaluate {
+            self.evaluate(goal_id).await;
+        }
+        self.release(goal_id).await;
+        if self.settle(goal_id) {
+            self.release(goal_id).await;
+        }
+    }
+
+    /// Submit the tasks the plan allows to run now
+    async fn release(&self, goal_id: Uuid) {
+        loop {
+            let (task, owner) = {
+                let Some(mut record) = self.goals.get_mut(&goal_id) else {
+                    return;
+                };
+                if record.state != GoalState::Active {
+                    return;
+                }
+                if record.plan.release == TaskRelease::Sequential && self.running(&record, GoalChainKind::Task) > 0 {
+                    return;
+                }
+                let Some(task) = record.pending.pop_front() else {
+                    return;
+                };
+                (task, record.owner.clone())
+            };
+
+            let neuron_id = match self.entry_neuron(&task.layer) {
+                Ok(neuron_id) => neuron_id,
+                Err(e) => {
+                    self.fail_submission(goal_id, e);
+                    return;
+                }
+            };
+            let signal = NeuronSignal::forward("goals", &neuron_id, "API", &task.layer, task.content);
+            if let Err(e) = self.spawn(goal_id, GoalChainKind::Task, neuron_id, owner, signal).await {
+                self.fail_submission(goal_id, e);
+                return;
+            }
+        }
+    }
+
+    /// Ask the evaluator to score the task outputs so far
+    async fn evaluate(&self, goal_id: Uuid) {
+        let Some(evaluator) = self.config.evaluator.clone() else {
+            return;
+        };
+        let (prompt, owner) = {
+            let Some(record) = self.goals.get(&goal_id) else {
+                return;
+            };
+            let outputs: Vec<String> = record
+                .chains
+                .iter()
+                .filter(|(_, kind, _)| *kind == GoalChainKind::Task)
+                .filter_map(|(chain_id, _, _)| self.chain_tracker.aggregate(chain_id)?.final_output)
+                .collect();
+            (evaluation_prompt(&record.goal, &outputs), record.owner.clone())
+        };
+
+        let layer = self
+            .entry_neurons
+            .iter()
+            .find(|(_, neuron_id)| *neuron_id == evaluator)
+            .map(|(layer, _)| layer.clone())
+            .unwrap_or_default();
+        let signal = NeuronSignal::forward("goals", &evaluator, "API", &layer, prompt);
+        if let Err(e) = self.spawn(goal_id, GoalChainKind::Evaluation, evaluator, owner, signal).await {
+            warn!("Goal {} evaluation not submitted: {}", goal_id, e);
+        }
+    }
+
+    /// Submit a root signal for the goal, registering its chain first so a
+    /// chain finishing immediately is still attributed
+    async fn spawn(
+        &self,
+        goal_id: Uuid,
+        kind: GoalChainKind,
+        neuron_id: String,
+        owner: Option<ChainOwner>,
+        mut signal: NeuronSignal,
+    ) -> ServerResult<()> {
+        signal.metadata.insert(GOAL_ID_KEY.to_string(), goal_id.to_string());
+        let chain_id = signal.signal_id.to_string();
+        self.chains.insert(chain_id.clone(), goal_id);
+        if let Some(mut record) = self.goals.get_mut(&goal_id) {
+            record.chains.push((chain_id.clone(), kind, neuron_id));
+        }
+
+        if let Err(e) = self.executor.submit(owner.as_ref(), signal).await {
+            self.chains.remove(&chain_id);
+            if let Some(mut record) = self.goals.get_mut(&goal_id) {
+                record.chains.retain(|(id, _, _)| *id != chain_id);
+            }
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    /// Settle a goal with nothing left to run: queue another attempt for an
+    /// adaptive goal still short of its criteria, otherwise close it. Returns
+    /// whether an attempt was queued.
+    fn settle(&self, goal_id: Uuid) -> bool {
+        let Some(mut record) = self.goals.get_mut(&goal_id) else {
+            return false;
+        };
+        if record.state != GoalState::Active
+            || !record.pending.is_empty()
+            || self.running(&record, GoalChainKind::Task) > 0
+            || self.running(&record, GoalChainKind::Evaluation) > 0
+        {
+            return false;
+        }
+
+        let unmet = record.unmet_criteria();
+        if record.plan.retry_until_achieved
+            && !unmet.is_empty()
+            && self.config.evaluator.is_some()
+            && record.rounds < self.config.max_rounds
+        {
+            let task = record.plan.retry_task(&record.goal, &unmet);
+            record.pending.push_back(task);
+            record.rounds += 1;
+            return true;
+        }
+
+        let all_failed = record
+            .chains
+            .iter()
+            .filter(|(_, kind, _)| *kind == GoalChainKind::Task)
+            .all(|(chain_id, _, _)| {
+                self.chain_tracker
+                    .get(chain_id)
+                    .is_none_or(|chain| chain.status != ChainStatus::Completed)
+            });
+        record.state = if all_failed { GoalState::Failed } else { GoalState::Completed };
+        record.updated_at = Utc::now();
+        info!("Goal {} finished as {:?}", goal_id, record.state);
+        false
+    }
+
+    /// Pause the goal if its spend, deadline or quality score break a constraint
+    fn check_constraints(&self, record: &mut GoalRecord) {
+        let cost: f64 = record
+            .chains
+            .iter()
+            .filter_map(|(chain_id, _, _)| self.chain_tracker.aggregate(chain_id))
+            .map(|result| self.price(&result))
+            .sum();
+
+        let mut violations = Vec::new();
+        for constraint in &record.goal.constraints {
+            match &constraint.constraint_type {
+                ConstraintType::Resource { max_cost } if cost > *max_cost as f64 => {
+                    violations.push(format!("cost ${:.4} exceeds budget ${:.4}", cost, max_cost));
+                }
+                ConstraintType::Time { deadline } if Utc::now() > *deadline => {
+                    violations.push(format!("deadline {} passed", deadline.to_rfc3339()));
+                }
+                ConstraintType::Quality { min_score } => {
+                    if let Some(score) = record.scores.get(QUALITY_KEY).filter(|score| **score < *min_score) {
+                        violations.push(format!("quality {} below minimum {}", score, min_score));
+                    }
+                }
+                _ => {}
+            }
+        }
+        for violation in violations {
+            record.pause(violation);
+        }
+    }
+
+    fn fail_submission(&self, goal_id: Uuid, error: ServerError) {
+        if let Some(mut record) = self.goals.get_mut(&goal_id) {
+            record.pause(format!("task submission failed: {}", error));
+            record.updated_at = Utc::now();
+        }
+    }
+
+    fn running(&self, record: &GoalRecord, kind: GoalChainKind) -> usize {
+        record
+            .chains
+            .iter()
+            .filter(|(chain_id, chain_kind, _)| {
+                *chain_kind == kind
+                    && self
+                        .chain_tracker
+                        .get(chain_id)
+                        .is_some_and(|chain| chain.status == ChainStatus::Running)
+            })
+            .count()
+    }
+
+    /// Neuron for a layer, falling back to the nearest configured layer
+    fn entry_neuron(&self, layer: &str) -> ServerResult<String> {
+        let depth = |layer: &str| layer.trim_start_matches('L').parse::<i32>().unwrap_or(0);
+        self.entry_neurons
+            .iter()
+            .min_by_key(|(candidate, _)| ((depth(candidate) - depth(layer)).abs(), -depth(candidate)))
+            .map(|(_, neuron_id)| neuron_id.clone())
+            .ok_or_else(|| ServerError::InvalidInput("No neurons are configured to work on goals".to_string()))
+    }
+
+    fn price(&self, result: &ChainResult) -> f64 {
+        let (prompt, completion) = self.pricing;
+        (result.prompt_tokens as f64 * prompt + result.completion_tokens as f64 * completion) / 1000.0
+    }
+}
+
+fn max_cost(goal: &Goal) -> Option<f32> {
+    goal.constraints.iter().find_map(|constraint| match constraint.constraint_type {
+        ConstraintType::Resource { max_cost } => Some(max_cost),
+        _ => None,
+    })
+}
@@ -1,28 +1,47 @@
 //! Enhanced health check system for HAL9 server
+//!
+//! Each dependency is checked by a [`HealthProbe`]. The [`HealthChecker`]
+//! runs probes concurrently, each under its own timeout, caches results for
+//! a short TTL so frequent polling doesn't hammer dependencies, and folds
+//! them into one status:
+//! - healthy: every probe passed
+//! - degraded: a non-critical dependency failed, or a critical one is slow
+//! - unhealthy: a critical dependency failed
 
+use async_trait::async_trait;
 use axum::{
     extract::{Query, State},
     response::{IntoResponse, Response},
     http::StatusCode,
     Json,
 };
+use dashmap::DashMap;
 use serde::{Deserialize, Serialize};
 use std::{
     collections::HashMap,
+    path::PathBuf,
     sync::Arc,
     time::{Duration, Instant},
 };
 use tokio::time::timeout;
 use tracing::warn;
 
+use hal9_api_types::HealthSummary;
+use hal9_core::config::{ClaudeConfig, HealthConfig};
+use hal9_core::memory::MemoryStore;
+
 use crate::{
     server::HAL9Server,
     error::ServerError,
-    database::DatabasePool,
+    degraded::{DegradedMode, DegradedTrigger},
+    layer_pause::LayerGate,
+    read_only::ReadOnlyGate,
+    metrics::Metrics,
+    neuron::NeuronRegistry,
 };
 
 /// Health check status
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum HealthStatus {
     Healthy,
@@ -31,6 +50,14 @@ pub enum HealthStatus {
 }
 
 impl HealthStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HealthStatus::Healthy => "healthy",
+            HealthStatus::Degraded => "degraded",
+            HealthStatus::Unhealthy => "unhealthy",
+        }
+    }
+    
     pub fn to_status_code(&self) -> StatusCode {
         match self {
             HealthStatus::Healthy => StatusCode::OK,
@@ -50,6 +77,19 @@ pub struct ComponentHealth {
     pub metadata: HashMap<String, serde_json::Value>,
 }
 
+impl ComponentHealth {
+    /// Result without latency or metadata, for probes to fill in
+    pub fn new(name: &str, status: HealthStatus, message: Option<String>) -> Self {
+        Self {
+            name: name.to_string(),
+            status,
+            message,
+            latency_ms: 0,
+            metadata: HashMap::new(),
+        }
+    }
+}
+
 /// Overall health check response
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct HealthCheckResponse {
@@ -57,6 +97,8 @@ pub struct HealthCheckResponse {
     pub timestamp: String,
     pub version: String,
     pub uptime_seconds: u64,
+    /// Refusing writes for maintenance
+    pub read_only: bool,
     pub components: Vec<ComponentHealth>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub checks_passed: Option<usize>,
@@ -70,178 +112,719 @@ pub struct HealthCheckQuery {
     /// Include detailed component checks
     #[serde(default)]
     pub detailed: bool,
-    /// Timeout for health checks in milliseconds
-    #[serde(default = "default_timeout")]
-    pub timeout_ms: u64,
 }
 
-fn default_timeout() -> u64 {
-    5000 // 5 seconds default
+/// A dependency check
+#[async_trait]
+pub trait HealthProbe: Send + Sync {
+    /// Component name in health reports
+    fn name(&self) -> &'static str;
+    
+    /// Whether this dependency failing makes the server unhealthy rather
+    /// than degraded
+    fn critical(&self) -> bool;
+    
+    async fn check(&self) -> ComponentHealth;
+}
+
+struct RegisteredProbe {
+    probe: Arc<dyn HealthProbe>,
+    timeout: Duration,
+}
+
+/// Outcome of running every probe
+#[derive(Debug, Clone)]
+pub struct HealthReport {
+    pub status: HealthStatus,
+    pub components: Vec<ComponentHealth>,
+}
+
+impl HealthReport {
+    /// Whether the server should receive traffic; degraded still serves
+    pub fn is_ready(&self) -> bool {
+        self.status != HealthStatus::Unhealthy
+    }
+    
+    pub fn component(&self, name: &str) -> Option<&ComponentHealth> {
+        self.components.iter().find(|c| c.name == name)
+    }
+}
+
+/// Runs registered probes under per-probe timeouts and caches their results
+pub struct HealthChecker {
+    config: HealthConfig,
+    probes: parking_lot::RwLock<Vec<RegisteredProbe>>,
+    cache: DashMap<&'static str, (Instant, ComponentHealth)>,
+}
+
+impl HealthChecker {
+    pub fn new(config: HealthConfig) -> Self {
+        Self {
+            config,
+            probes: parking_lot::RwLock::new(Vec::new()),
+            cache: DashMap::new(),
+        }
+    }
+    
+    /// Add a probe, replacing any probe of the same name
+    pub fn register(&self, probe: Arc<dyn HealthProbe>) {
+        let timeout = Duration::from_millis(self.config.timeout_ms(probe.name()));
+        let mut probes = self.probes.write();
+        probes.retain(|p| p.probe.name() != probe.name());
+        self.cache.remove(probe.name());
+        probes.push(RegisteredProbe { probe, timeout });
+    }
+    
+    /// Check every dependency, reusing results younger than the cache TTL
+    pub async fn check(&self) -> HealthReport {
+        let probes: Vec<(Arc<dyn HealthProbe>, Duration)> = self.probes.read()
+            .iter()
+            .map(|p| (p.probe.clone(), p.timeout))
+            .collect();
+        
+        let results = futures::future::join_all(
+            probes.iter().map(|(probe, probe_timeout)| self.run(probe.as_ref(), *probe_timeout))
+        ).await;
+        
+        let status = probes.iter()
+            .zip(&results)
+            .map(|((probe, _), health)| match health.status {
+                HealthStatus::Unhealthy if !probe.critical() => HealthStatus::Degraded,
+                status => status,
+            })
+            .max()
+            .unwrap_or(HealthStatus::Healthy);
+        
+        HealthReport { status, components: results }
+    }
+    
+    async fn run(&self, probe: &dyn HealthProbe, probe_timeout: Duration) -> ComponentHealth {
+        let ttl = Duration::from_millis(self.config.cache_ttl_ms);
+        if let Some(cached) = self.cache.get(probe.name()) {
+            if cached.0.elapsed() < ttl {
+                return cached.1.clone();
+            }
+        }
+        
+        let start = Instant::now();
+        let health = match timeout(probe_timeout, probe.check()).await {
+            Ok(mut health) => {
+                health.latency_ms = start.elapsed().as_millis() as u64;
+                health
+            }
+            Err(_) => {
+                let mut health = ComponentHealth::new(
+                    probe.name(),
+                    HealthStatus::Unhealthy,
+                    Some(format!("Check timed out after {}ms", probe_timeout.as_millis())),
+                );
+                health.latency_ms = probe_timeout.as_millis() as u64;
+                health
+            }
+        };
+        
+        self.cache.insert(probe.name(), (Instant::now(), health.clone()));
+        health
+    }
+}
+
+/// Database connectivity and round-trip latency
+pub struct DatabaseProbe {
+    pool: sqlx::SqlitePool,
+    slow: Duration,
+}
+
+impl DatabaseProbe {
+    pub fn new(pool: sqlx::SqlitePool, config: &HealthConfig) -> Self {
+        Self { pool, slow: Duration::from_millis(config.database_slow_ms) }
+    }
+}
+
+#[async_trait]
+impl HealthProbe for DatabaseProbe {
+    fn name(&self) -> &'static str {
+        "database"
+    }
+    
+    fn critical(&self) -> bool {
+        true
+    }
+    
+    async fn check(&self) -> ComponentHealth {
+        let start = Instant::now();
+        // Takes a shared lock, so a database held locked by a writer fails here
+        let result: Result<i64, _> = sqlx::query_scalar("SELECT count(*) FROM sqlite_master")
+            .fetch_one(&self.pool)
+            .await;
+        let latency = start.elapsed();
+        
+        let mut health = match result {
+            Err(e) => ComponentHealth::new(
+                self.name(),
+                HealthStatus::Unhealthy,
+                Some(format!("Database query failed: {}", e)),
+            ),
+            Ok(_) if latency > self.slow => ComponentHealth::new(
+                self.name(),
+                HealthStatus::Degraded,
+                Some(format!("Slow database: {}ms", latency.as_millis())),
+            ),
+            Ok(_) => ComponentHealth::new(self.name(), HealthStatus::Healthy, None),
+        };
+        health.metadata.insert("pool_size".to_string(), serde_json::json!(self.pool.size()));
+        health.metadata.insert("idle_connections".to_string(), serde_json::json!(self.pool.num_idle()));
+        health
+    }
+}
+
+/// Claude provider reachability. A recent successful response proves it
+/// without a request; otherwise the API endpoint gets a HEAD request.
+pub struct ClaudeProbe {
+    mode: String,
+    fallback_to_mock: bool,
+    endpoint: String,
+    max_success_age: Duration,
+    metrics: Arc<Metrics>,
+    client: reqwest::Client,
+}
+
+impl ClaudeProbe {
+    pub fn new(claude: &ClaudeConfig, config: &HealthConfig, metrics: Arc<Metrics>) -> Self {
+        Self {
+            mode: claude.mode.clone(),
+            fallback_to_mock: claude.fallback_to_mock,
+            endpoint: config.claude_endpoint.clone(),
+            max_success_age: Duration::from_secs(config.claude_success_max_age_secs),
+            metrics,
+            client: reqwest::Client::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl HealthProbe for ClaudeProbe {
+    fn name(&self) -> &'static str {
+        "claude"
+    }
+    
+    /// Hybrid mode and API mode with fallback keep answering from the mock
+    fn critical(&self) -> bool {
+        self.mode == "api" && !self.fallback_to_mock
+    }
+    
+    async fn check(&self) -> ComponentHealth {
+        let mut health = ComponentHealth::new(self.name(), HealthStatus::Healthy, None);
+        health.metadata.insert("mode".to_string(), serde_json::json!(self.mode));
+        if self.mode == "mock" {
+            return health;
+        }
+        
+        if let Some(age) = self.metrics.provider_success_age() {
+            if age <= self.max_success_age {
+                health.metadata.insert("last_success_secs".to_string(), serde_json::json!(age.as_secs()));
+                return health;
+            }
+        }
+        
+        // Any HTTP response, even an error status, means the API is reachable
+        match self.client.head(&self.endpoint).send().await {
+            Ok(response) => {
+                health.metadata.insert("http_status".to_string(), serde_json::json!(response.status().as_u16()));
+            }
+            Err(e) => {
+                health.status = HealthStatus::Unhealthy;
+                health.message = Some(format!("Claude API unreachable: {}", e));
+            }
+        }
+        health
+    }
+}
+
+/// Neuron memory backend
+pub struct MemoryBackendProbe {
+    store: Arc<dyn MemoryStore>,
+}
+
+impl MemoryBackendProbe {
+    pub fn new(store: Arc<dyn MemoryStore>) -> Self {
+        Self { store }
+    }
+}
+
+#[async_trait]
+impl HealthProbe for MemoryBackendProbe {
+    fn name(&self) -> &'static str {
+        "memory"
+    }
+    
+    /// Neurons keep working without memory context
+    fn critical(&self) -> bool {
+        false
+    }
+    
+    async fn check(&self) -> ComponentHealth {
+        match self.store.namespace_usage(None).await {
+            Ok(usage) => {
+                let mut health = ComponentHealth::new(self.name(), HealthStatus::Healthy, None);
+                let entries: u64 = usage.iter().map(|u| u.entries).sum();
+                health.metadata.insert("namespaces".to_string(), serde_json::json!(usage.len()));
+                health.metadata.insert("entries".to_string(), serde_json::json!(entries));
+                health
+            }
+            Err(e) => ComponentHealth::new(
+                self.name(),
+                HealthStatus::Unhealthy,
+                Some(format!("Memory store unavailable: {}", e)),
+            ),
+        }
+    }
+}
+
+/// Plugin runtime, unhealthy when every plugin has failed
+#[cfg(feature = "plugins")]
+pub struct PluginRuntimeProbe {
+    manager: Arc<crate::plugins::PluginManager>,
+}
+
+#[cfg(feature = "plugins")]
+impl PluginRuntimeProbe {
+    pub fn new(manager: Arc<crate::plugins::PluginManager>) -> Self {
+        Self { manager }
+    }
+}
+
+/// Status of a plugin runtime whose plugins are in `states`
+#[cfg(feature = "plugins")]
+pub fn plugin_runtime_health(states: &[crate::plugins::manager::PluginState]) -> ComponentHealth {
+    use crate::plugins::manager::PluginState;
+    
+    let failed: Vec<&str> = states.iter()
+        .filter_map(|s| match s {
+            PluginState::Failed(reason) => Some(reason.as_str()),
+            _ => None,
+        })
+        .collect();
+    let (status, message) = if failed.is_empty() {
+        (HealthStatus::Healthy, None)
+    } else if failed.len() == states.len() {
+        (HealthStatus::Unhealthy, Some(format!("All {} plugins failed: {}", failed.len(), failed.join("; "))))
+    } else {
+        (HealthStatus::Degraded, Some(format!("{} of {} plugins failed: {}", failed.len(), states.len(), failed.join("; "))))
+    };
+    
+    let mut health = ComponentHealth::new("plugins", status, message);
+    health.metadata.insert("total".to_string(), serde_json::json!(states.len()));
+    health.metadata.insert("failed".to_string(), serde_json::json!(failed.len()));
+    health
+}
+
+#[cfg(feature = "plugins")]
+#[async_trait]
+impl HealthProbe for PluginRuntimeProbe {
+    fn name(&self) -> &'static str {
+        "plugins"
+    }
+    
+    fn critical(&self) -> bool {
+        false
+    }
+    
+    async fn check(&self) -> ComponentHealth {
+        let states: Vec<_> = self.manager.list_plugins().await.into_iter().map(|p| p.state).collect();
+        plugin_runtime_health(&states)
+    }
+}
+
+/// Free space on the volumes holding SQLite databases
+pub struct DiskProbe {
+    paths: Vec<PathBuf>,
+    degraded_percent: f64,
+    unhealthy_percent: f64,
+}
+
+impl DiskProbe {
+    pub fn new(paths: Vec<PathBuf>, config: &HealthConfig) -> Self {
+        Self {
+            paths,
+            degraded_percent: config.disk_degraded_percent,
+            unhealthy_percent: config.disk_unhealthy_percent,
+        }
+    }
+    
+    /// Directories of the SQLite databases `config` uses
+    pub fn database_dirs(config: &hal9_core::ServerConfig) -> Vec<PathBuf> {
+        let mut files = Vec::new();
+        if config.memory.enabled {
+            files.push(&config.memory.database_path);
+        }
+        if config.auth.enabled {
+            files.push(&config.auth.database_path);
+        }
+        if let Some(path) = &config.retention.signal_log_path {
+            files.push(path);
+        }
+        
+        let mut dirs: Vec<PathBuf> = Vec::new();
+        for file in files {
+            let dir = std::path::Path::new(file)
+                .parent()
+                .filter(|p| !p.as_os_str().is_empty())
+                .map(|p| p.to_path_buf())
+                .unwrap_or_else(|| PathBuf::from("."));
+            if !dirs.contains(&dir) {
+                dirs.push(dir);
+            }
+        }
+        dirs
+    }
+}
+
+#[async_trait]
+impl HealthProbe for DiskProbe {
+    fn name(&self) -> &'static str {
+        "disk"
+    }
+    
+    /// SQLite writes fail on a full disk
+    fn critical(&self) -> bool {
+        true
+    }
+    
+    async fn check(&self) -> ComponentHealth {
+        let mut health = ComponentHealth::new(self.name(), HealthStatus::Healthy, None);
+        let mut messages = Vec::new();
+        
+        for path in &self.paths {
+            let key = path.display().to_string();
+            let space = fs2::available_space(path).and_then(|available| Ok((available, fs2::total_space(path)?)));
+            let (status, message) = match space {
+                Ok((available, total)) => {
+                    let usage_percent = if total == 0 { 100.0 } else { (total - available) as f64 / total as f64 * 100.0 };
+                    health.metadata.insert(key.clone(), serde_json::json!({
+                        "available_gb": available / 1_073_741_824,
+                        "total_gb": total / 1_073_741_824,
+                        "usage_percent": usage_percent,
+                    }));
+                    if usage_percent > self.unhealthy_percent {
+                        (HealthStatus::Unhealthy, Some(format!("{}: disk {:.1}% full", key, usage_percent)))
+                    } else if usage_percent > self.degraded_percent {
+                        (HealthStatus::Degraded, Some(format!("{}: disk {:.1}% full", key, usage_percent)))
+                    } else {
+                        (HealthStatus::Healthy, None)
+                    }
+                }
+                Err(e) => (HealthStatus::Unhealthy, Some(format!("{}: failed to check disk space: {}", key, e))),
+            };
+            health.status = health.status.max(status);
+            messages.extend(message);
+        }
+        
+        if !messages.is_empty() {
+            health.message = Some(messages.join("; "));
+        }
+        health
+    }
+}
+
+/// Local neurons, unhealthy without any or when half are failing
+pub struct NeuronsProbe {
+    registry: Arc<NeuronRegistry>,
+}
+
+impl NeuronsProbe {
+    pub fn new(registry: Arc<NeuronRegistry>) -> Self {
+        Self { registry }
+    }
+}
+
+#[async_trait]
+impl HealthProbe for NeuronsProbe {
+    fn name(&self) -> &'static str {
+        "neurons"
+    }
+    
+    fn critical(&self) -> bool {
+        true
+    }
+    
+    async fn check(&self) -> ComponentHealth {
+        let neurons = self.registry.list_all().await;
+        let total_neurons = neurons.len();
+        let healthy_neurons = neurons.iter().filter(|n| n.is_healthy).count();
+        let unhealthy_neurons = total_neurons - healthy_neurons;
+        
+        let mut layer_counts = HashMap::new();
+        for neuron in &neurons {
+            *layer_counts.entry(neuron.layer.clone()).or_insert(0) += 1;
+        }
+        
+        let (status, message) = if total_neurons == 0 {
+            (HealthStatus::Unhealthy, Some("No neurons initialized".to_string()))
+        } else if unhealthy_neurons == 0 {
+            (HealthStatus::Healthy, None)
+        } else if unhealthy_neurons < total_neurons / 2 {
+            (HealthStatus::Degraded, Some(format!("{} unhealthy neurons", unhealthy_neurons)))
+        } else {
+            (HealthStatus::Unhealthy, Some(format!("{} unhealthy neurons", unhealthy_neurons)))
+        };
+        
+        let mut health = ComponentHealth::new(self.name(), status, message);
+        health.metadata.insert("total".to_string(), serde_json::json!(total_neurons));
+        health.metadata.insert("healthy".to_string(), serde_json::json!(healthy_neurons));
+        health.metadata.insert("unhealthy".to_string(), serde_json::json!(unhealthy_neurons));
+        health.metadata.insert("layer_distribution".to_string(), serde_json::json!(layer_counts));
+        health
+    }
+}
+
+/// Operator-paused layers; degraded while any layer is paused
+pub struct LayerPauseProbe {
+    gate: Arc<LayerGate>,
+}
+
+impl LayerPauseProbe {
+    pub fn new(gate: Arc<LayerGate>) -> Self {
+        Self { gate }
+    }
+}
+
+#[async_trait]
+impl HealthProbe for LayerPauseProbe {
+    fn name(&self) -> &'static str {
+        "layers"
+    }
+    
+    fn critical(&self) -> bool {
+        false
+    }
+    
+    async fn check(&self) -> ComponentHealth {
+        let paused = self.gate.paused();
+        let (status, message) = if paused.is_empty() {
+            (HealthStatus::Healthy, None)
+        } else {
+            let layers: Vec<_> = paused.iter().map(|p| p.pause.layer.as_str()).collect();
+            (HealthStatus::Degraded, Some(format!("Paused layers: {}", layers.join(", "))))
+        };
+        
+        let mut health = ComponentHealth::new(self.name(), status, message);
+        health.metadata.insert("paused".to_string(), serde_json::json!(paused));
+        health
+    }
+}
+
+/// Degraded mode; degraded while neurons answer with templates and park
+/// signals instead of calling their providers
+pub struct DegradedProbe {
+    degraded: Arc<DegradedMode>,
 }
 
-/// Simple health check endpoint (fast)
-pub async fn health_check_simple() -> impl IntoResponse {
-    Json(serde_json::json!({
-        "status": "healthy",
-        "service": "hal9-server",
-        "version": env!("CARGO_PKG_VERSION"),
-        "timestamp": chrono::Utc::now().to_rfc3339(),
+impl DegradedProbe {
+    pub fn new(degraded: Arc<DegradedMode>) -> Self {
+        Self { degraded }
+    }
+}
+
+#[async_trait]
+impl HealthProbe for DegradedProbe {
+    fn name(&self) -> &'static str {
+        "providers"
+    }
+    
+    fn critical(&self) -> bool {
+        false
+    }
+    
+    async fn check(&self) -> ComponentHealth {
+        let status = self.degraded.status();
+        let (health_status, message) = match status.trigger {
+            None => (HealthStatus::Healthy, None),
+            Some(DegradedTrigger::Outage) => (
+                HealthStatus::Degraded,
+                Some(format!("Every provider is down, {} signals parked", status.parked)),
+            ),
+            Some(DegradedTrigger::Forced) => (
+                HealthStatus::Degraded,
+                Some(format!("Degraded mode forced, {} signals parked", status.parked)),
+            ),
+        };
+        
+        let mut health = ComponentHealth::new(self.name(), health_status, message);
+        health.metadata.insert("degraded".to_string(), serde_json::json!(status));
+        health
+    }
+}
+
+/// Read-only mode; degraded while the API refuses writes
+pub struct ReadOnlyProbe {
+    gate: Arc<ReadOnlyGate>,
+}
+
+impl ReadOnlyProbe {
+    pub fn new(gate: Arc<ReadOnlyGate>) -> Self {
+        Self { gate }
+    }
+}
+
+#[async_trait]
+impl HealthProbe for ReadOnlyProbe {
+    fn name(&self) -> &'static str {
+        "read_only"
+    }
+    
+    fn critical(&self) -> bool {
+        false
+    }
+    
+    async fn check(&self) -> ComponentHealth {
+        let status = self.gate.status();
+        let (health, message) = match &status.state {
+            None => (HealthStatus::Healthy, None),
+            Some(state) => (
+                HealthStatus::Degraded,
+                Some(format!(
+                    "Read-only since {}: {}",
+                    state.since.to_rfc3339(),
+                    state.reason.as_deref().unwrap_or("no reason given")
+                )),
+            ),
+        };
+        
+        let mut health = ComponentHealth::new(self.name(), health, message);
+        health.metadata.insert("read_only".to_string(), serde_json::json!(status));
+        health
+    }
+}
+
+/// Optional Redis cache
+pub struct RedisProbe {
+    url: String,
+}
+
+impl RedisProbe {
+    pub fn new(url: String) -> Self {
+        Self { url }
+    }
+}
+
+#[async_trait]
+impl HealthProbe for RedisProbe {
+    fn name(&self) -> &'static str {
+        "redis"
+    }
+    
+    fn critical(&self) -> bool {
+        false
+    }
+    
+    async fn check(&self) -> ComponentHealth {
+        check_redis_health(&self.url).await
+    }
+}
+
+/// Host memory usage
+pub struct SystemMemoryProbe;
+
+#[async_trait]
+impl HealthProbe for SystemMemoryProbe {
+    fn name(&self) -> &'static str {
+        "system_memory"
+    }
+    
+    fn critical(&self) -> bool {
+        false
+    }
+    
+    async fn check(&self) -> ComponentHealth {
+        check_memory_health()
+    }
+}
+
+/// Health summary without per-dependency detail
+pub async fn health_check_simple(
+    State(server): State<Arc<HAL9Server>>,
+) -> impl IntoResponse {
+    let report = server.health().check().await;
+    (report.status.to_status_code(), Json(HealthSummary {
+        status: report.status.as_str().to_string(),
+        service: "hal9-server".to_string(),
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        timestamp: chrono::Utc::now().to_rfc3339(),
+        read_only: server.read_only().is_active(),
     }))
 }
 
 /// Liveness probe endpoint (Kubernetes)
+///
+/// Only answers whether the process is serving requests. It never consults
+/// dependencies: an outage elsewhere must not get a healthy process
+/// restarted.
 pub async fn liveness_probe() -> impl IntoResponse {
-    // Basic liveness check - is the service running?
     (StatusCode::OK, "OK")
 }
 
 /// Readiness probe endpoint (Kubernetes)
+///
+/// Ready unless a critical dependency is down or the server is draining;
+/// degraded servers still take traffic.
 pub async fn readiness_probe(
     State(server): State<Arc<HAL9Server>>,
 ) -> impl IntoResponse {
-    // Check if server is ready to accept traffic
-    let start = Instant::now();
-    
-    // Quick database connectivity check
-    // TODO: Add database support to HAL9Server
-    // if let Some(db) = &server.db {
-    //     match timeout(Duration::from_secs(2), check_database_ready(db)).await {
-    //         Ok(Ok(_)) => {},
-    //         Ok(Err(_)) | Err(_) => {
-    //             return (StatusCode::SERVICE_UNAVAILABLE, "Database not ready");
-    //         }
-    //     }
-    // }
-    
-    // Check if neurons are initialized
-    let neurons = server.list_neurons().await.unwrap_or_default();
-    if neurons.is_empty() {
-        return (StatusCode::SERVICE_UNAVAILABLE, "No neurons initialized");
+    // A draining server finishes its chains but takes no new traffic
+    if server.autoscaler().is_draining() {
+        return (StatusCode::SERVICE_UNAVAILABLE, "Not ready: draining".to_string());
     }
     
+    let start = Instant::now();
+    let report = server.health().check().await;
+    
     let latency = start.elapsed().as_millis();
     if latency > 1000 {
         warn!("Readiness probe slow: {}ms", latency);
     }
     
-    (StatusCode::OK, "Ready")
+    if report.is_ready() {
+        return (StatusCode::OK, "Ready".to_string());
+    }
+    let failing: Vec<&str> = report.components.iter()
+        .filter(|c| c.status == HealthStatus::Unhealthy)
+        .map(|c| c.name.as_str())
+        .collect();
+    (StatusCode::SERVICE_UNAVAILABLE, format!("Not ready: {}", failing.join(", ")))
 }
 
-/// Comprehensive health check endpoint
+/// Per-dependency health, `GET /health/detail`
+pub async fn health_check_detail(
+    State(server): State<Arc<HAL9Server>>,
+) -> Result<Response, ServerError> {
+    health_response(&server, true).await
+}
+
+/// Comprehensive health check endpoint; components only with `?detailed=true`
 pub async fn health_check_detailed(
     State(server): State<Arc<HAL9Server>>,
     Query(params): Query<HealthCheckQuery>,
 ) -> Result<Response, ServerError> {
+    health_response(&server, params.detailed).await
+}
+
+async fn health_response(server: &HAL9Server, detailed: bool) -> Result<Response, ServerError> {
     let start = Instant::now();
-    let check_timeout = Duration::from_millis(params.timeout_ms);
-    
-    // Get server status
     let status = server.get_status().await?;
-    let mut components = Vec::new();
-    let mut overall_status = HealthStatus::Healthy;
-    
-    if params.detailed {
-        // Database health check
-        // TODO: Add database support to HAL9Server
-        // if let Some(db) = &server.db {
-        //     let db_health = timeout(
-        //         check_timeout,
-        //         check_database_health(db.clone())
-        //     ).await;
-        //     
-        //     match db_health {
-        //         Ok(Ok(health)) => {
-        //             if health.status != HealthStatus::Healthy {
-        //                 overall_status = HealthStatus::Degraded;
-        //             }
-        //             components.push(health);
-        //         },
-        //         Ok(Err(e)) => {
-        //             overall_status = HealthStatus::Unhealthy;
-        //             components.push(ComponentHealth {
-        //                 name: "database".to_string(),
-        //                 status: HealthStatus::Unhealthy,
-        //                 message: Some(format!("Database check failed: {}", e)),
-        //                 latency_ms: check_timeout.as_millis() as u64,
-        //                 metadata: HashMap::new(),
-        //             });
-        //         },
-        //         Err(_) => {
-        //             overall_status = HealthStatus::Unhealthy;
-        //             components.push(ComponentHealth {
-        //                 name: "database".to_string(),
-        //                 status: HealthStatus::Unhealthy,
-        //                 message: Some("Database check timed out".to_string()),
-        //                 latency_ms: check_timeout.as_millis() as u64,
-        //                 metadata: HashMap::new(),
-        //             });
-        //         }
-        //     }
-        // }
-        
-        // Redis health check
-        if let Ok(redis_url) = std::env::var("REDIS_URL") {
-            let redis_health = timeout(
-                check_timeout,
-                check_redis_health(&redis_url)
-            ).await;
-            
-            match redis_health {
-                Ok(Ok(health)) => {
-                    if health.status != HealthStatus::Healthy {
-                        overall_status = HealthStatus::Degraded;
-                    }
-                    components.push(health);
-                },
-                Ok(Err(_)) | Err(_) => {
-                    // Redis is optional, so just mark as degraded
-                    if overall_status == HealthStatus::Healthy {
-                        overall_status = HealthStatus::Degraded;
-                    }
-                    components.push(ComponentHealth {
-                        name: "redis".to_string(),
-                        status: HealthStatus::Degraded,
-                        message: Some("Redis unavailable".to_string()),
-                        latency_ms: 0,
-                        metadata: HashMap::new(),
-                    });
-                }
-            }
-        }
-        
-        // Neuron health checks
-        let neuron_health = check_neurons_health(&server, &status).await;
-        if neuron_health.status != HealthStatus::Healthy {
-            overall_status = neuron_health.status;
-        }
-        components.push(neuron_health);
-        
-        // Memory health check
-        let memory_health = check_memory_health();
-        if memory_health.status != HealthStatus::Healthy && overall_status == HealthStatus::Healthy {
-            overall_status = HealthStatus::Degraded;
-        }
-        components.push(memory_health);
-        
-        // Disk space health check
-        let disk_health = check_disk_health();
-        if disk_health.status != HealthStatus::Healthy && overall_status == HealthStatus::Healthy {
-            overall_status = HealthStatus::Degraded;
-        }
-        components.push(disk_health);
-    }
+    let report = server.health().check().await;
     
-    let checks_passed = components.iter().filter(|c| c.status == HealthStatus::Healthy).count();
-    let checks_total = components.len();
+    let checks_passed = report.components.iter().filter(|c| c.status == HealthStatus::Healthy).count();
+    let checks_total = report.components.len();
     
     let response = HealthCheckResponse {
-        status: overall_status,
+        status: report.status,
         timestamp: chrono::Utc::now().to_rfc3339(),
         version: env!("CARGO_PKG_VERSION").to_string(),
         uptime_seconds: status.uptime.as_secs(),
-        components,
-        checks_passed: if params.detailed { Some(checks_passed) } else { None },
-        checks_total: if params.detailed { Some(checks_total) } else { None },
+        read_only: status.read_only.active,
+        components: if detailed { report.components } else { Vec::new() },
+        checks_passed: if detailed { Some(checks_passed) } else { None },
+        checks_total: if detailed { Some(checks_total) } else { None },
     };
     
     let elapsed = start.elapsed();
@@ -249,33 +832,11 @@ pub async fn health_check_detailed(
         warn!("Health check took {}ms", elapsed.as_millis());
     }
     
-    Ok((overall_status.to_status_code(), Json(response)).into_response())
-}
-
-/// Check database health
-// TODO: Implement when database support is added to HAL9Server
-#[allow(dead_code)]
-async fn check_database_health(_db: Arc<DatabasePool>) -> Result<ComponentHealth, ServerError> {
-    // Placeholder implementation until database support is added
-    Ok(ComponentHealth {
-        name: "database".to_string(),
-        status: HealthStatus::Healthy,
-        message: Some("Database check not implemented".to_string()),
-        latency_ms: 0,
-        metadata: HashMap::new(),
-    })
-}
-
-/// Quick database readiness check
-// TODO: Implement when database support is added to HAL9Server
-#[allow(dead_code)]
-async fn check_database_ready(_db: &DatabasePool) -> Result<(), ServerError> {
-    // Placeholder implementation
-    Ok(())
+    Ok((report.status.to_status_code(), Json(response)).into_response())
 }
 
 /// Check Redis health
-async fn check_redis_health(redis_url: &str) -> Result<ComponentHealth, ServerError> {
+async fn check_redis_health(redis_url: &str) -> ComponentHealth {
     let start = Instant::now();
     let mut metadata = HashMap::new();
     
@@ -307,83 +868,39 @@ async fn check_redis_health(redis_url: &str) -> Result<ComponentHealth, ServerEr
                             }
                             
                             let latency = start.elapsed().as_millis() as u64;
-                            Ok(ComponentHealth {
+                            ComponentHealth {
                                 name: "redis".to_string(),
                                 status: HealthStatus::Healthy,
                                 message: None,
                                 latency_ms: latency,
                                 metadata,
-                            })
+                            }
                         },
-                        Err(e) => Ok(ComponentHealth {
+                        Err(e) => ComponentHealth {
                             name: "redis".to_string(),
                             status: HealthStatus::Unhealthy,
                             message: Some(format!("Ping failed: {}", e)),
                             latency_ms: start.elapsed().as_millis() as u64,
                             metadata,
-                        })
+                        }
                     }
                 },
-                Err(e) => Ok(ComponentHealth {
+                Err(e) => ComponentHealth {
                     name: "redis".to_string(),
                     status: HealthStatus::Unhealthy,
                     message: Some(format!("Connection failed: {}", e)),
                     latency_ms: start.elapsed().as_millis() as u64,
                     metadata,
-                })
+                }
             }
         },
-        Err(e) => Ok(ComponentHealth {
+        Err(e) => ComponentHealth {
             name: "redis".to_string(),
             status: HealthStatus::Unhealthy,
             message: Some(format!("Client creation failed: {}", e)),
             latency_ms: start.elapsed().as_millis() as u64,
             metadata,
-        })
-    }
-}
-
-/// Check neuron health
-async fn check_neurons_health(
-    _server: &HAL9Server,
-    status: &crate::server::ExtendedServerStatus,
-) -> ComponentHealth {
-    let start = Instant::now();
-    let mut metadata = HashMap::new();
-    
-    let total_neurons = status.neurons.len();
-    let healthy_neurons = status.neurons.iter().filter(|n| n.is_healthy).count();
-    let unhealthy_neurons = total_neurons - healthy_neurons;
-    
-    metadata.insert("total".to_string(), serde_json::json!(total_neurons));
-    metadata.insert("healthy".to_string(), serde_json::json!(healthy_neurons));
-    metadata.insert("unhealthy".to_string(), serde_json::json!(unhealthy_neurons));
-    
-    // Check layer distribution
-    let mut layer_counts = HashMap::new();
-    for neuron in &status.neurons {
-        *layer_counts.entry(neuron.layer.clone()).or_insert(0) += 1;
-    }
-    metadata.insert("layer_distribution".to_string(), serde_json::json!(layer_counts));
-    
-    let status = if unhealthy_neurons == 0 {
-        HealthStatus::Healthy
-    } else if unhealthy_neurons < total_neurons / 2 {
-        HealthStatus::Degraded
-    } else {
-        HealthStatus::Unhealthy
-    };
-    
-    ComponentHealth {
-        name: "neurons".to_string(),
-        status,
-        message: if unhealthy_neurons > 0 {
-            Some(format!("{} unhealthy neurons", unhealthy_neurons))
-        } else {
-            None
-        },
-        latency_ms: start.elapsed().as_millis() as u64,
-        metadata,
+        }
     }
 }
 
@@ -415,7 +932,7 @@ fn check_memory_health() -> ComponentHealth {
     };
     
     ComponentHealth {
-        name: "memory".to_string(),
+        name: "system_memory".to_string(),
         status,
         message: if memory_percent > 80.0 {
             Some(format!("High memory usage: {:.1}%", memory_percent))
@@ -427,64 +944,6 @@ fn check_memory_health() -> ComponentHealth {
     }
 }
 
-/// Check disk space health
-fn check_disk_health() -> ComponentHealth {
-    let mut metadata = HashMap::new();
-    
-    // Check disk space for data directory
-    let data_dir = std::env::var("HAL9_DATA_DIR").unwrap_or_else(|_| "/app/data".to_string());
-    
-    match fs2::available_space(&data_dir) {
-        Ok(available_bytes) => {
-            match fs2::total_space(&data_dir) {
-                Ok(total_bytes) => {
-                    let used_bytes = total_bytes - available_bytes;
-                    let usage_percent = (used_bytes as f64 / total_bytes as f64) * 100.0;
-                    
-                    metadata.insert("path".to_string(), serde_json::json!(data_dir));
-                    metadata.insert("total_gb".to_string(), serde_json::json!(total_bytes / 1_073_741_824));
-                    metadata.insert("available_gb".to_string(), serde_json::json!(available_bytes / 1_073_741_824));
-                    metadata.insert("usage_percent".to_string(), serde_json::json!(usage_percent));
-                    
-                    let status = if usage_percent > 95.0 {
-                        HealthStatus::Unhealthy
-                    } else if usage_percent > 85.0 {
-                        HealthStatus::Degraded
-                    } else {
-                        HealthStatus::Healthy
-                    };
-                    
-                    ComponentHealth {
-                        name: "disk".to_string(),
-                        status,
-                        message: if usage_percent > 85.0 {
-                            Some(format!("High disk usage: {:.1}%", usage_percent))
-                        } else {
-                            None
-                        },
-                        latency_ms: 0,
-                        metadata,
-                    }
-                },
-                Err(e) => ComponentHealth {
-                    name: "disk".to_string(),
-                    status: HealthStatus::Unhealthy,
-                    message: Some(format!("Failed to get total space: {}", e)),
-                    latency_ms: 0,
-                    metadata,
-                }
-            }
-        },
-        Err(e) => ComponentHealth {
-            name: "disk".to_string(),
-            status: HealthStatus::Unhealthy,
-            message: Some(format!("Failed to check disk space: {}", e)),
-            latency_ms: 0,
-            metadata,
-        }
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -511,4 +970,10 @@ mod tests {
         assert_eq!(HealthStatus::Degraded.to_status_code(), StatusCode::OK);
         assert_eq!(HealthStatus::Unhealthy.to_status_code(), StatusCode::SERVICE_UNAVAILABLE);
     }
-}
\ No newline at end of file
+    
+    #[test]
+    fn test_health_status_ordering() {
+        assert!(HealthStatus::Healthy < HealthStatus::Degraded);
+        assert!(HealthStatus::Degraded < HealthStatus::Unhealthy);
+    }
+}
@@ -0,0 +1,117 @@
+//! Idempotency keys for signal submission
+//!
+//! A client whose submission timed out can't tell whether the server started
+//! the chain. Sending the same `Idempotency-Key` header on every attempt makes
+//! the server answer repeats with the response of the first successful one
+//! instead of starting another chain. Keys are scoped to the route and the
+//! caller and remembered for a fixed TTL; only successful responses are
+//! stored, so a failed attempt can be retried under the same key.
+//!
+//! Two attempts racing each other can both miss the cache. Clients retry
+//! sequentially, so this only matters for callers reusing a key in parallel.
+
+use dashmap::DashMap;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+use std::time::{Duration, Instant};
+
+use axum::http::HeaderMap;
+use hal9_api_types::IDEMPOTENCY_KEY_HEADER;
+
+use crate::chain_limits::ChainOwner;
+
+/// How long a key is remembered
+pub const DEFAULT_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Keys remembered at most; the oldest are dropped first
+pub const DEFAULT_CAPACITY: usize = 10_000;
+
+/// Longest key accepted; longer keys are ignored rather than stored
+pub const MAX_KEY_LENGTH: usize = 255;
+
+struct Entry {
+    stored_at: Instant,
+    response: Value,
+}
+
+/// Responses of recent requests, by idempotency key
+pub struct IdempotencyCache {
+    entries: DashMap<String, Entry>,
+    ttl: Duration,
+    capacity: usize,
+}
+
+impl Default for IdempotencyCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_TTL, DEFAULT_CAPACITY)
+    }
+}
+
+impl IdempotencyCache {
+    pub fn new(ttl: Duration, capacity: usize) -> Self {
+        Self {
+            entries: DashMap::new(),
+            ttl,
+            capacity: capacity.max(1),
+        }
+    }
+
+    /// Cache key for a request to `route`, or `None` if the request carries
+    /// no usable idempotency key
+    pub fn key_for(headers: &HeaderMap, route: &str, owner: Option<&ChainOwner>) -> Option<String> {
+        let key = headers.get(IDEMPOTENCY_KEY_HEADER)?.to_str().ok()?.trim();
+        if key.is_empty() || key.len() > MAX_KEY_LENGTH {
+            return None;
+        }
+        let caller = owner.map(|o| o.user_id.as_str()).unwrap_or("anonymous");
+        Some(format!("{}:{}:{}", route, caller, key))
+    }
+
+    /// Response stored under `key`, if it hasn't expired
+    pub fn get<T: DeserializeOwned>(&self, key: &str) -> Option<T> {
+        let fresh = self.entries
+            .get(key)
+            .map(|entry| (entry.stored_at.elapsed() < self.ttl).then(|| entry.response.clone()))?;
+        match fresh {
+            Some(response) => serde_json::from_value(response).ok(),
+            None => {
+                self.entries.remove(key);
+                None
+            }
+        }
+    }
+
+    pub fn insert<T: Serialize>(&self, key: String, response: &T) {
+        let Ok(response) = serde_json::to_value(response) else {
+            return;
+        };
+        if self.entries.len() >= self.capacity && !self.entries.contains_key(&key) {
+            self.evict();
+        }
+        self.entries.insert(key, Entry { stored_at: Instant::now(), response });
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Drop expired entries, or the oldest one if none has expired
+    fn evict(&self) {
+        let ttl = self.ttl;
+        self.entries.retain(|_, entry| entry.stored_at.elapsed() < ttl);
+        if self.entries.len() < self.capacity {
+            return;
+        }
+        let oldest = self.entries
+            .iter()
+            .min_by_key(|entry| entry.stored_at)
+            .map(|entry| entry.key().clone());
+        if let Some(oldest) = oldest {
+            self.entries.remove(&oldest);
+        }
+    }
+}
@@ -0,0 +1,174 @@
+//! Kafka consumers of ingestion sources
+//!
+//! Offsets are committed by hand, each once its message was taken in,
+//! duplicated or quarantined, so a crash redelivers what was in flight and
+//! the dedup window absorbs it. A held message stays on the topic: its
+//! partition is rewound to it and every assigned partition paused until the
+//! hold is expected to lift, then offered again.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use rdkafka::config::ClientConfig;
+use rdkafka::consumer::{CommitMode, Consumer, StreamConsumer};
+use rdkafka::error::KafkaResult;
+use rdkafka::message::Message;
+use rdkafka::{Offset, TopicPartitionList};
+use tokio::time::Instant;
+use tracing::{error, info, warn};
+
+use hal9_core::config::{IngestSourceConfig, IngestTransport};
+
+use super::{Hold, Ingested, Ingestion};
+
+/// How often a held consumer checks whether it may resume
+const TICK: Duration = Duration::from_secs(1);
+
+/// How often lag is measured
+const LAG_INTERVAL: Duration = Duration::from_secs(10);
+
+/// How long fetching a partition's watermarks may take
+const WATERMARK_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Consume the topic of Kafka source `config` until the process exits
+pub async fn consume(ingestion: Arc<Ingestion>, config: IngestSourceConfig) {
+    let IngestTransport::Kafka { brokers, topic, group, settings } = &config.transport else {
+        return;
+    };
+    let name = &config.name;
+    let consumer = match connect(brokers, topic, group, settings) {
+        Ok(consumer) => Arc::new(consumer),
+        Err(e) => {
+            error!("Ingestion source {} cannot consume {}: {}", name, topic, e);
+            return;
+        }
+    };
+    info!("Ingestion source {} consuming {} as {}", name, topic, group);
+
+    // Offset each rewound partition restarts from; messages after it that
+    // were fetched before the rewind are skipped until it comes round again
+    let mut rewound: HashMap<(String, i32), i64> = HashMap::new();
+    let mut held_until: Option<Instant> = None;
+    let mut next_lag = Instant::now();
+    let mut tick = tokio::time::interval(TICK);
+
+    loop {
+        tokio::select! {
+            received = consumer.recv() => {
+                let (topic, partition, offset, payload) = match received {
+                    Ok(message) => (
+                        message.topic().to_string(),
+                        message.partition(),
+                        message.offset(),
+                        message.payload().map(<[u8]>::to_vec).unwrap_or_default(),
+                    ),
+                    Err(e) => {
+                        warn!("Ingestion source {} failed to receive: {}", name, e);
+                        tokio::time::sleep(TICK).await;
+                        continue;
+                    }
+                };
+                let position = (topic, partition);
+                if let Some(&from) = rewound.get(&position) {
+                    if offset > from {
+                        continue;
+                    }
+                    rewound.remove(&position);
+                }
+
+                // Messages arriving while held go back without being offered
+                let retry_after = if held_until.is_some() {
+                    Some(Duration::ZERO)
+                } else {
+                    match ingestion.ingest(name, &payload).await {
+                        Ok(Ingested::Held { hold }) => Some(hold.retry_after()),
+                        Ok(_) => None,
+                        Err(e) => {
+                            error!("Ingestion source {} failed to handle offset {}: {}", name, offset, e);
+                            Some(Hold::Unavailable { error: e.to_string() }.retry_after())
+                        }
+                    }
+                };
+
+                let (topic, partition) = &position;
+                let result = match retry_after {
+                    None => commit(&consumer, topic, *partition, offset + 1),
+                    Some(retry_after) => {
+                        let until = Instant::now() + retry_after;
+                        held_until = Some(held_until.map_or(until, |held| held.max(until)));
+                        rewound.insert(position.clone(), offset);
+                        consumer
+                            .seek(topic, *partition, Offset::Offset(offset), Duration::ZERO)
+                            .and_then(|_| set_paused(&consumer, true))
+                    }
+                };
+                if let Err(e) = result {
+                    warn!("Ingestion source {} failed at {}/{}@{}: {}", name, position.0, position.1, offset, e);
+                }
+            }
+            _ = tick.tick() => {
+                let may_resume = held_until.is_some_and(|until| Instant::now() >= until) && !ingestion.is_paused(name);
+                if may_resume {
+                    held_until = None;
+                    if let Err(e) = set_paused(&consumer, false) {
+                        warn!("Ingestion source {} failed to resume: {}", name, e);
+                    }
+                }
+                if Instant::now() >= next_lag {
+                    next_lag = Instant::now() + LAG_INTERVAL;
+                    let measured = consumer.clone();
+                    if let Ok(Ok(lag)) = tokio::task::spawn_blocking(move || measure_lag(&measured)).await {
+                        ingestion.set_lag(name, lag);
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn connect(brokers: &str, topic: &str, group: &str, settings: &HashMap<String, String>) -> KafkaResult<StreamConsumer> {
+    let mut client = ClientConfig::new();
+    client
+        .set("bootstrap.servers", brokers)
+        .set("group.id", group)
+        .set("enable.auto.commit", "false")
+        .set("auto.offset.reset", "earliest");
+    for (key, value) in settings {
+        client.set(key, value);
+    }
+    let consumer: StreamConsumer = client.create()?;
+    consumer.subscribe(&[topic])?;
+    Ok(consumer)
+}
+
+fn commit(consumer: &StreamConsumer, topic: &str, partition: i32, next: i64) -> KafkaResult<()> {
+    let mut offsets = TopicPartitionList::new();
+    offsets.add_partition_offset(topic, partition, Offset::Offset(next))?;
+    consumer.commit(&offsets, CommitMode::Async)
+}
+
+fn set_paused(consumer: &StreamConsumer, paused: bool) -> KafkaResult<()> {
+    let assignment = consumer.assignment()?;
+    if paused {
+        consumer.pause(&assignment)
+    } else {
+        consumer.resume(&assignment)
+    }
+}
+
+/// Messages on the assigned partitions past the consumer's position; blocks
+/// while watermarks are fetched
+fn measure_lag(consumer: &StreamConsumer) -> KafkaResult<u64> {
+    let positions = consumer.position()?;
+    let mut lag = 0;
+    for element in positions.elements() {
+        let (low, high) = consumer.fetch_watermarks(element.topic(), element.partition(), WATERMARK_TIMEOUT)?;
+        let position = match element.offset() {
+            Offset::Offset(offset) => offset,
+            _ => low,
+        };
+        lag += (high - position).max(0) as u64;
+    }
+    Ok(lag)
+}
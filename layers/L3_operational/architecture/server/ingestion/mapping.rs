@@ -0,0 +1,149 @@
+//! Templates mapping inbound JSON messages to signals
+//!
+//! A template is text in which `{{/path}}` stands for the value at that
+//! JSON pointer in the message: strings as they are, anything else as JSON.
+//! A pointer the message lacks, or one pointing at null, fails the mapping
+//! and the message is quarantined.
+
+use sha2::{Digest, Sha256};
+use serde_json::Value;
+
+use hal9_core::config::IngestMapping;
+use hal9_core::{Error, Result};
+
+use crate::cost_tags::{self, CostTags};
+use crate::webhooks::to_hex;
+
+/// What a message maps to
+#[derive(Debug, Clone, PartialEq)]
+pub struct MappedMessage {
+    pub to: String,
+    pub from: String,
+    pub content: String,
+    /// The source's tags with those taken from the message
+    pub tags: CostTags,
+    pub dedup_key: String,
+}
+
+/// Check that every placeholder of `template` is closed and a JSON pointer
+pub fn check_template(template: &str) -> Result<()> {
+    render(template, &Value::Null, true).map(|_| ())
+}
+
+/// Render `template` against `message`
+pub fn render_template(template: &str, message: &Value) -> Result<String> {
+    render(template, message, false)
+}
+
+fn render(template: &str, message: &Value, check_only: bool) -> Result<String> {
+    let mut output = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(open) = rest.find("{{") {
+        output.push_str(&rest[..open]);
+        let after = &rest[open + 2..];
+        let close = after
+            .find("}}")
+            .ok_or_else(|| Error::InvalidInput(format!("Unclosed placeholder in template '{}'", template)))?;
+        let pointer = after[..close].trim();
+        if !pointer.is_empty() && !pointer.starts_with('/') {
+            return Err(Error::InvalidInput(format!(
+                "Placeholder '{}' in template '{}' is not a JSON pointer such as /field",
+                pointer, template
+            )));
+        }
+        if !check_only {
+            match message.pointer(pointer) {
+                None | Some(Value::Null) => {
+                    return Err(Error::InvalidInput(format!("Message has no value at {}", pointer)));
+                }
+                Some(Value::String(value)) => output.push_str(value),
+                Some(value) => output.push_str(&value.to_string()),
+            }
+        }
+        rest = &after[close + 2..];
+    }
+    output.push_str(rest);
+    Ok(output)
+}
+
+/// Map a raw message of the source named `source`. Fails, with the reason
+/// the message is quarantined for, when it is not JSON or lacks a value a
+/// template needs.
+pub fn map_message(
+    mapping: &IngestMapping,
+    source: &str,
+    source_tags: &CostTags,
+    payload: &[u8],
+) -> Result<MappedMessage> {
+    let message: Value = serde_json::from_slice(payload)
+        .map_err(|e| Error::InvalidInput(format!("Message is not JSON: {}", e)))?;
+
+    let mut tags = source_tags.clone();
+    for (key, template) in &mapping.tags {
+        tags.insert(key.clone(), render_template(template, &message)?);
+    }
+    cost_tags::check(&tags)?;
+
+    let dedup_key = match &mapping.dedup_key {
+        Some(template) => render_template(template, &message)?,
+        None => to_hex(&Sha256::digest(payload)),
+    };
+    let content = render_template(&mapping.content, &message)?;
+    if content.trim().is_empty() {
+        return Err(Error::InvalidInput("Message maps to empty content".to_string()));
+    }
+
+    Ok(MappedMessage {
+        to: render_template(&mapping.to, &message)?,
+        from: match &mapping.from {
+            Some(template) => render_template(template, &message)?,
+            None => source.to_string(),
+        },
+        content,
+        tags,
+        dedup_key,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_templates_render_strings_and_json() {
+        let message = json!({"issue": {"title": "Crash on save", "number": 42, "labels": ["bug"]}});
+        let rendered = render_template("#{{/issue/number}} {{ /issue/title }} {{/issue/labels}}", &message).unwrap();
+        assert_eq!(rendered, "#42 Crash on save [\"bug\"]");
+        assert!(render_template("{{/issue/body}}", &message).is_err());
+        assert!(check_template("{{/issue").is_err());
+        assert!(check_template("{{issue}}").is_err());
+        assert!(check_template("plain text").is_ok());
+    }
+
+    #[test]
+    fn test_messages_map_with_source_tags_and_a_hash_key() {
+        let mapping = IngestMapping {
+            to: "planner".to_string(),
+            content: "Triage: {{/title}}".to_string(),
+            from: None,
+            tags: HashMap::from([("team".to_string(), "{{/team}}".to_string())]),
+            dedup_key: None,
+        };
+        let source_tags = CostTags::from([("source".to_string(), "github".to_string())]);
+        let payload = br#"{"title": "Crash", "team": "core"}"#;
+
+        let mapped = map_message(&mapping, "github", &source_tags, payload).unwrap();
+        assert_eq!((mapped.to.as_str(), mapped.from.as_str()), ("planner", "github"));
+        assert_eq!(mapped.content, "Triage: Crash");
+        assert_eq!(cost_tags::encode(&mapped.tags), "source=github,team=core");
+        assert_eq!(mapped.dedup_key.len(), 64);
+        assert_eq!(map_message(&mapping, "github", &source_tags, payload).unwrap().dedup_key, mapped.dedup_key);
+
+        let err = map_message(&mapping, "github", &source_tags, b"not json").unwrap_err();
+        assert!(err.to_string().contains("not JSON"), "{}", err);
+        let err = map_message(&mapping, "github", &source_tags, br#"{"title": "x", "team": "a b"}"#).unwrap_err();
+        assert!(err.to_string().contains("Invalid cost tag value"), "{}", err);
+    }
+}
@@ -0,0 +1,531 @@
+//! Ingestion of chains from Kafka topics and inbound webhooks
+//!
+//! Each configured source maps its JSON messages to the root signal of a
+//! chain (see [`mapping`]) and submits it the way the API does, tagged with
+//! the source's cost tags. A message whose dedup key was taken in within
+//! `dedup_window_secs` starts no second chain, so Kafka redeliveries and
+//! webhook retries are harmless. Messages that are not JSON, lack a value
+//! the mapping needs or map to a signal the server refuses are written to
+//! the [`quarantine`] table with the reason. Everything else that cannot be
+//! taken in right away is held rather than dropped:
+//!
+//! - while the source is paused through the admin API,
+//! - while the server is read-only,
+//! - once the source took `max_per_minute` messages in the last minute,
+//! - once chains carrying the source's tags spent its budget for the
+//!   period, until the period resets.
+//!
+//! Kafka consumers keep held messages on the topic and commit an offset
+//! only once its message was handled, see [`kafka`]. Webhook senders are
+//! answered 429 or 503 with a `Retry-After`, see [`webhook`].
+
+#[cfg(feature = "kafka")]
+pub mod kafka;
+pub mod mapping;
+pub mod quarantine;
+pub mod webhook;
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use axum::http::HeaderMap;
+use chrono::{DateTime, FixedOffset, Utc};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use hal9_core::config::{CostTagConfig, IngestSourceConfig, IngestTransport, IngestionConfig};
+use hal9_core::metadata_schema::keys;
+use hal9_core::sqlite::{SqlitePools, SqliteTuning};
+use hal9_core::{Error, NeuronSignal, Result, ServerConfig};
+
+use crate::budget_period::{next_reset, period_start};
+use crate::cost_tags::{self, CostTags, TAGS_KEY};
+use crate::cost_tracker::CostTracker;
+use crate::error::{ServerError, ServerResult};
+use crate::idempotency::IdempotencyCache;
+use crate::rate_limiter::{RateLimitConfig, RateLimitError, RateLimiter};
+use crate::read_only::ReadOnlyGate;
+use crate::server::SignalSubmitter;
+
+use self::mapping::MappedMessage;
+use self::quarantine::{QuarantinedMessage, Quarantine};
+
+/// Metadata key naming the source a chain was ingested from
+pub const SOURCE_KEY: &str = keys::ingest::SOURCE;
+
+/// Metadata key carrying the key a message was deduplicated by
+pub const MESSAGE_KEY_KEY: &str = keys::ingest::MESSAGE_KEY;
+
+/// Dedup keys remembered at most; the oldest are dropped first
+pub const DEDUP_CAPACITY: usize = 100_000;
+
+/// How soon a held message is offered again while its source is paused,
+/// the server is read-only or chains cannot be started
+const HOLD_RECHECK: Duration = Duration::from_secs(5);
+
+/// Window throughput is measured over
+const THROUGHPUT_WINDOW: Duration = Duration::from_secs(60);
+
+/// Why a source is not taking messages in
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "reason", rename_all = "snake_case")]
+pub enum Hold {
+    /// Paused through the admin API
+    Paused,
+    /// The server is read-only
+    ReadOnly,
+    /// The source took `max_per_minute` messages in the last minute
+    RateLimited { retry_after_secs: u64 },
+    /// Chains carrying the source's tags spent its budget
+    OverBudget { spent_usd: f64, budget_usd: f64, resets_at: DateTime<Utc> },
+    /// The chain could not be started, e.g. while the server drains
+    Unavailable { error: String },
+}
+
+impl Hold {
+    /// How long until the message is worth offering again
+    pub fn retry_after(&self) -> Duration {
+        match self {
+            Hold::RateLimited { retry_after_secs } => Duration::from_secs((*retry_after_secs).max(1)),
+            Hold::OverBudget { resets_at, .. } => (*resets_at - Utc::now())
+                .to_std()
+                .unwrap_or_default()
+                .max(Duration::from_secs(1)),
+            Hold::Paused | Hold::ReadOnly | Hold::Unavailable { .. } => HOLD_RECHECK,
+        }
+    }
+}
+
+/// What became of an inbound message
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "outcome", rename_all = "snake_case")]
+pub enum Ingested {
+    /// A chain was started
+    Accepted { chain_id: String },
+    /// The dedup key was taken in within the window, starting `chain_id`
+    Duplicate { chain_id: String },
+    /// The message was kept in the quarantine table instead
+    Quarantined { quarantine_id: String, error: String },
+    /// Nothing was taken in; the message is to be offered again later
+    Held { hold: Hold },
+}
+
+impl Ingested {
+    /// Outcome name in metrics
+    pub fn outcome(&self) -> &'static str {
+        match self {
+            Ingested::Accepted { .. } => "accepted",
+            Ingested::Duplicate { .. } => "duplicate",
+            Ingested::Quarantined { .. } => "quarantined",
+            Ingested::Held { .. } => "held",
+        }
+    }
+}
+
+/// Throughput, lag and state of one source
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SourceStatus {
+    pub name: String,
+    /// `kafka` or `webhook`
+    pub transport: String,
+    pub paused: bool,
+    /// Why the last message was held, until one is taken in again
+    pub held: Option<Hold>,
+    pub accepted: u64,
+    pub duplicates: u64,
+    pub quarantined: u64,
+    /// Times a message was held
+    pub held_messages: u64,
+    /// Webhook deliveries refused for a bad signature
+    pub rejected: u64,
+    /// Chains started per second over the last minute
+    pub throughput_per_sec: f64,
+    /// Messages on the topic not handled yet; Kafka sources only
+    pub lag: Option<u64>,
+}
+
+/// Check that sources have unique names, valid templates and tags the
+/// cost tracker accepts, and that Kafka sources can be served by this build
+pub fn validate(config: &IngestionConfig, tags: &CostTagConfig) -> Result<()> {
+    let invalid = |message: String| Error::Config(format!("Invalid ingestion config: {}", message));
+    if config.dedup_window_secs == 0 && !config.sources.is_empty() {
+        return Err(invalid("dedup_window_secs must be positive".to_string()));
+    }
+
+    let mut names = Vec::new();
+    for source in &config.sources {
+        let name = &source.name;
+        let valid_name = !name.is_empty() && name.bytes().all(|b| b.is_ascii_alphanumeric() || b"-_".contains(&b));
+        if !valid_name {
+            return Err(invalid(format!("source name '{}' must be letters, digits, - or _", name)));
+        }
+        if names.contains(&name) {
+            return Err(invalid(format!("source '{}' is configured twice", name)));
+        }
+        names.push(name);
+
+        let mapping = &source.mapping;
+        let templates = [Some(&mapping.to), Some(&mapping.content), mapping.from.as_ref(), mapping.dedup_key.as_ref()];
+        for template in templates.into_iter().flatten().chain(mapping.tags.values()) {
+            mapping::check_template(template).map_err(|e| invalid(format!("source '{}': {}", name, e)))?;
+        }
+        let static_tags: CostTags = source.tags.clone().into_iter().collect();
+        cost_tags::check(&static_tags).map_err(|e| invalid(format!("source '{}': {}", name, e)))?;
+        if let Some(key) = source.tags.keys().chain(mapping.tags.keys()).find(|key| !tags.allowed_keys.contains(*key)) {
+            return Err(invalid(format!(
+                "source '{}' tags chains with '{}', which claude.cost_controls.tags.allowed_keys does not list",
+                name, key
+            )));
+        }
+
+        if source.max_per_minute == Some(0) {
+            return Err(invalid(format!("source '{}' needs a positive max_per_minute", name)));
+        }
+        if let Some(budget) = &source.budget {
+            if !budget.usd.is_finite() || budget.usd < 0.0 {
+                return Err(invalid(format!("source '{}' budget {} must not be negative", name, budget.usd)));
+            }
+            if source.tags.is_empty() {
+                return Err(invalid(format!("source '{}' needs tags to count its budget against", name)));
+            }
+        }
+
+        match &source.transport {
+            IngestTransport::Kafka { brokers, topic, group, .. } => {
+                if cfg!(not(feature = "kafka")) {
+                    return Err(invalid(format!("Kafka source '{}' needs a build with the kafka feature", name)));
+                }
+                if brokers.is_empty() || topic.is_empty() || group.is_empty() {
+                    return Err(invalid(format!("Kafka source '{}' needs brokers, topic and group", name)));
+                }
+            }
+            IngestTransport::Webhook { secret_env, .. } => {
+                if secret_env.is_empty() {
+                    return Err(invalid(format!("webhook source '{}' needs secret_env", name)));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Counters and state of one source
+struct Source {
+    config: IngestSourceConfig,
+    tags: CostTags,
+    /// HMAC secret of a webhook source
+    secret: Option<String>,
+    limiter: Option<RateLimiter>,
+    paused: AtomicBool,
+    hold: Mutex<Option<Hold>>,
+    accepted: AtomicU64,
+    duplicates: AtomicU64,
+    quarantined: AtomicU64,
+    held: AtomicU64,
+    rejected: AtomicU64,
+    /// When chains were started within the throughput window
+    recent: Mutex<VecDeque<Instant>>,
+    lag: Mutex<Option<u64>>,
+}
+
+impl Source {
+    fn new(config: &IngestSourceConfig) -> Result<Self> {
+        let secret = match &config.transport {
+            IngestTransport::Webhook { secret_env, .. } => Some(std::env::var(secret_env).map_err(|_| {
+                Error::Config(format!(
+                    "Invalid ingestion config: webhook source '{}' has no secret in {}",
+                    config.name, secret_env
+                ))
+            })?),
+            IngestTransport::Kafka { .. } => None,
+        };
+        let limiter = config.max_per_minute.map(|max_requests| {
+            RateLimiter::new(RateLimitConfig {
+                max_requests,
+                window_duration: Duration::from_secs(60),
+                burst_size: 0,
+                ..Default::default()
+            })
+        });
+        Ok(Self {
+            tags: config.tags.clone().into_iter().collect(),
+            config: config.clone(),
+            secret,
+            limiter,
+            paused: AtomicBool::new(false),
+            hold: Mutex::new(None),
+            accepted: AtomicU64::new(0),
+            duplicates: AtomicU64::new(0),
+            quarantined: AtomicU64::new(0),
+            held: AtomicU64::new(0),
+            rejected: AtomicU64::new(0),
+            recent: Mutex::new(VecDeque::new()),
+            lag: Mutex::new(None),
+        })
+    }
+
+    fn record(&self, ingested: &Ingested) {
+        let counter = match ingested {
+            Ingested::Accepted { .. } => {
+                self.recent.lock().push_back(Instant::now());
+                &self.accepted
+            }
+            Ingested::Duplicate { .. } => &self.duplicates,
+            Ingested::Quarantined { .. } => &self.quarantined,
+            Ingested::Held { .. } => &self.held,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        *self.hold.lock() = match ingested {
+            Ingested::Held { hold } => Some(hold.clone()),
+            _ => None,
+        };
+    }
+
+    fn status(&self) -> SourceStatus {
+        let started = {
+            let mut recent = self.recent.lock();
+            while recent.front().is_some_and(|at| at.elapsed() > THROUGHPUT_WINDOW) {
+                recent.pop_front();
+            }
+            recent.len()
+        };
+        let paused = self.paused.load(Ordering::Relaxed);
+        SourceStatus {
+            name: self.config.name.clone(),
+            transport: match self.config.transport {
+                IngestTransport::Kafka { .. } => "kafka",
+                IngestTransport::Webhook { .. } => "webhook",
+            }
+            .to_string(),
+            paused,
+            held: if paused { Some(Hold::Paused) } else { self.hold.lock().clone() },
+            accepted: self.accepted.load(Ordering::Relaxed),
+            duplicates: self.duplicates.load(Ordering::Relaxed),
+            quarantined: self.quarantined.load(Ordering::Relaxed),
+            held_messages: self.held.load(Ordering::Relaxed),
+            rejected: self.rejected.load(Ordering::Relaxed),
+            throughput_per_sec: started as f64 / THROUGHPUT_WINDOW.as_secs_f64(),
+            lag: *self.lag.lock(),
+        }
+    }
+}
+
+/// The server's ingestion sources
+pub struct Ingestion {
+    sources: BTreeMap<String, Arc<Source>>,
+    quarantine: Quarantine,
+    dedup: IdempotencyCache,
+    submitter: SignalSubmitter,
+    cost_tracker: Arc<CostTracker>,
+    read_only: Arc<ReadOnlyGate>,
+    /// Layer of each configured neuron, by ID
+    layers: HashMap<String, String>,
+}
+
+impl Ingestion {
+    /// Ingestion of the sources in `config`, with their quarantine table
+    /// opened; `None` without any sources
+    pub async fn open(
+        config: &ServerConfig,
+        submitter: SignalSubmitter,
+        cost_tracker: Arc<CostTracker>,
+        read_only: Arc<ReadOnlyGate>,
+    ) -> Result<Option<Self>> {
+        let ingestion = &config.ingestion;
+        if ingestion.sources.is_empty() {
+            return Ok(None);
+        }
+        let mut sources = BTreeMap::new();
+        for source in &ingestion.sources {
+            sources.insert(source.name.clone(), Arc::new(Source::new(source)?));
+        }
+
+        let path = &ingestion.quarantine_path;
+        if let Some(dir) = Path::new(path).parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir)
+                .map_err(|e| Error::Storage(format!("Failed to create {}: {}", dir.display(), e)))?;
+        }
+        let pools = SqlitePools::connect(&format!("sqlite:{}?mode=rwc", path), SqliteTuning::default()).await?;
+        let quarantine = Quarantine::new(pools);
+        quarantine.initialize().await?;
+
+        info!("Ingesting chains from {}", sources.keys().cloned().collect::<Vec<_>>().join(", "));
+        Ok(Some(Self {
+            sources,
+            quarantine,
+            dedup: IdempotencyCache::new(Duration::from_secs(ingestion.dedup_window_secs), DEDUP_CAPACITY),
+            submitter,
+            cost_tracker,
+            read_only,
+            layers: config.neurons.iter().map(|neuron| (neuron.id.clone(), neuron.layer.clone())).collect(),
+        }))
+    }
+
+    /// Start consuming the Kafka sources
+    pub fn start(self: &Arc<Self>) {
+        #[cfg(feature = "kafka")]
+        for source in self.sources.values() {
+            if let IngestTransport::Kafka { .. } = &source.config.transport {
+                tokio::spawn(kafka::consume(self.clone(), source.config.clone()));
+            }
+        }
+    }
+
+    /// Pools of the quarantine table
+    pub fn quarantine_pools(&self) -> &SqlitePools {
+        self.quarantine.pools()
+    }
+
+    fn source(&self, name: &str) -> ServerResult<&Arc<Source>> {
+        self.sources
+            .get(name)
+            .ok_or_else(|| ServerError::NotFound(format!("Ingestion source '{}' not found", name)))
+    }
+
+    /// Throughput, lag and state of every source, by name
+    pub fn status(&self) -> Vec<SourceStatus> {
+        self.sources.values().map(|source| source.status()).collect()
+    }
+
+    /// Stop taking messages in from a source until it is resumed
+    pub fn pause(&self, name: &str, actor: Option<&str>) -> ServerResult<SourceStatus> {
+        let source = self.source(name)?;
+        if !source.paused.swap(true, Ordering::Relaxed) {
+            info!("Ingestion source {} paused by {}", name, actor.unwrap_or("anonymous"));
+        }
+        Ok(source.status())
+    }
+
+    /// Take messages in from a paused source again
+    pub fn resume(&self, name: &str, actor: Option<&str>) -> ServerResult<SourceStatus> {
+        let source = self.source(name)?;
+        if source.paused.swap(false, Ordering::Relaxed) {
+            info!("Ingestion source {} resumed by {}", name, actor.unwrap_or("anonymous"));
+            *source.hold.lock() = None;
+        }
+        Ok(source.status())
+    }
+
+    /// Whether a source is paused
+    pub fn is_paused(&self, name: &str) -> bool {
+        self.sources.get(name).is_some_and(|source| source.paused.load(Ordering::Relaxed))
+    }
+
+    /// Record the number of messages a Kafka source has yet to handle
+    pub fn set_lag(&self, name: &str, lag: u64) {
+        if let Some(source) = self.sources.get(name) {
+            *source.lag.lock() = Some(lag);
+        }
+    }
+
+    /// Newest quarantined messages first, of one source if given
+    pub async fn quarantined(&self, source: Option<&str>) -> ServerResult<Vec<QuarantinedMessage>> {
+        if let Some(name) = source {
+            self.source(name)?;
+        }
+        self.quarantine.list(source).await.map_err(|e| ServerError::Internal(e.to_string()))
+    }
+
+    /// Check a webhook delivery's signature against its source's secret
+    pub fn verify_webhook(&self, name: &str, headers: &HeaderMap, body: &[u8]) -> ServerResult<()> {
+        let source = self.source(name)?;
+        let (IngestTransport::Webhook { signature_header, timestamp_header, .. }, Some(secret)) =
+            (&source.config.transport, &source.secret)
+        else {
+            return Err(ServerError::NotFound(format!("Ingestion source '{}' is not a webhook", name)));
+        };
+        let verified = webhook::verify(secret, signature_header, timestamp_header.as_deref(), headers, body, Utc::now());
+        if let Err(reason) = verified {
+            source.rejected.fetch_add(1, Ordering::Relaxed);
+            warn!("Refused delivery to webhook source {}: {}", name, reason);
+            return Err(ServerError::Forbidden(reason));
+        }
+        Ok(())
+    }
+
+    /// Take one raw message of source `name` in
+    pub async fn ingest(&self, name: &str, payload: &[u8]) -> ServerResult<Ingested> {
+        let source = self.source(name)?;
+        let ingested = self.take_in(source, payload).await?;
+        source.record(&ingested);
+        Ok(ingested)
+    }
+
+    async fn take_in(&self, source: &Source, payload: &[u8]) -> ServerResult<Ingested> {
+        if source.paused.load(Ordering::Relaxed) {
+            return Ok(Ingested::Held { hold: Hold::Paused });
+        }
+        if self.read_only.is_active() {
+            return Ok(Ingested::Held { hold: Hold::ReadOnly });
+        }
+
+        let name = &source.config.name;
+        let mapped = mapping::map_message(&source.config.mapping, name, &source.tags, payload);
+        let (signal, dedup_key) = match mapped.and_then(|mapped| self.signal(name, mapped)) {
+            Ok(signal) => signal,
+            Err(Error::InvalidInput(error)) => return self.quarantine(name, payload, &error).await,
+            Err(e) => return self.quarantine(name, payload, &e.to_string()).await,
+        };
+
+        let key = format!("ingest:{}:{}", name, dedup_key);
+        if let Some(chain_id) = self.dedup.get::<String>(&key) {
+            return Ok(Ingested::Duplicate { chain_id });
+        }
+        if let Some(hold) = self.over_budget(source) {
+            return Ok(Ingested::Held { hold });
+        }
+        if let Some(limiter) = &source.limiter {
+            if let Err(RateLimitError::TooManyRequests { retry_after }) = limiter.check_rate_limit(name).await {
+                let hold = Hold::RateLimited { retry_after_secs: retry_after.as_secs() };
+                return Ok(Ingested::Held { hold });
+            }
+        }
+
+        match self.submitter.submit_signal_as(None, signal).await {
+            Ok(chain_id) => {
+                self.dedup.insert(key, &chain_id);
+                Ok(Ingested::Accepted { chain_id })
+            }
+            Err(ServerError::InvalidInput(error)) => self.quarantine(name, payload, &error).await,
+            Err(e) => Ok(Ingested::Held { hold: Hold::Unavailable { error: e.to_string() } }),
+        }
+    }
+
+    /// Root signal of a mapped message, and its dedup key
+    fn signal(&self, source: &str, mapped: MappedMessage) -> Result<(NeuronSignal, String)> {
+        let layer = self.layers.get(&mapped.to)
+            .ok_or_else(|| Error::InvalidInput(format!("Message maps to unknown neuron '{}'", mapped.to)))?;
+        let mut signal = NeuronSignal::forward(&mapped.from, &mapped.to, "API", layer, mapped.content);
+        signal.metadata.insert(SOURCE_KEY.to_string(), source.to_string());
+        signal.metadata.insert(MESSAGE_KEY_KEY.to_string(), mapped.dedup_key.clone());
+        if !mapped.tags.is_empty() {
+            signal.metadata.insert(TAGS_KEY.to_string(), cost_tags::encode(&mapped.tags));
+        }
+        Ok((signal, mapped.dedup_key))
+    }
+
+    /// Hold on a source whose tagged chains spent its budget
+    fn over_budget(&self, source: &Source) -> Option<Hold> {
+        let budget = source.config.budget.as_ref()?;
+        let spent_usd = self.cost_tracker.spend_with_tags(&source.tags, budget.period).cost_usd;
+        if spent_usd < budget.usd {
+            return None;
+        }
+        let utc = FixedOffset::east_opt(0).unwrap();
+        let resets_at = next_reset(budget.period, utc, period_start(budget.period, utc, Utc::now()));
+        Some(Hold::OverBudget { spent_usd, budget_usd: budget.usd, resets_at })
+    }
+
+    async fn quarantine(&self, source: &str, payload: &[u8], error: &str) -> ServerResult<Ingested> {
+        let kept = self.quarantine
+            .insert(source, payload, error)
+            .await
+            .map_err(|e| ServerError::Internal(e.to_string()))?;
+        warn!("Quarantined message {} of ingestion source {}: {}", kept.id, source, error);
+        Ok(Ingested::Quarantined { quarantine_id: kept.id, error: kept.error })
+    }
+}
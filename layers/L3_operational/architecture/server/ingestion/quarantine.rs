@@ -0,0 +1,117 @@
+//! Inbound messages that could not become signals, with the reason why
+
+use chrono::{DateTime, TimeZone, Utc};
+use serde::Serialize;
+use sqlx::Row;
+
+use hal9_core::{sqlite::SqlitePools, Error, Result};
+
+/// Quarantined messages listed at most
+pub const MAX_LISTED: i64 = 1000;
+
+/// A message kept instead of ingested
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct QuarantinedMessage {
+    pub id: String,
+    pub source: String,
+    /// The raw message, lossily decoded as UTF-8
+    pub payload: String,
+    pub error: String,
+    pub received_at: DateTime<Utc>,
+}
+
+fn storage_error(e: sqlx::Error) -> Error {
+    Error::Storage(format!("Ingestion quarantine: {}", e))
+}
+
+/// The `ingest_quarantine` table
+pub struct Quarantine {
+    pools: SqlitePools,
+}
+
+impl Quarantine {
+    pub fn new(pools: SqlitePools) -> Self {
+        Self { pools }
+    }
+
+    pub fn pools(&self) -> &SqlitePools {
+        &self.pools
+    }
+
+    /// Create the quarantine table
+    pub async fn initialize(&self) -> Result<()> {
+        let statements = [
+            r#"
+            CREATE TABLE IF NOT EXISTS ingest_quarantine (
+                id TEXT PRIMARY KEY,
+                source TEXT NOT NULL,
+                payload TEXT NOT NULL,
+                error TEXT NOT NULL,
+                received_at INTEGER NOT NULL
+            )
+            "#,
+            "CREATE INDEX IF NOT EXISTS idx_ingest_quarantine_source ON ingest_quarantine(source, received_at)",
+        ];
+        for statement in statements {
+            sqlx::query(statement).execute(self.pools.writer()).await.map_err(storage_error)?;
+        }
+        Ok(())
+    }
+
+    /// Keep `payload` of `source`, refused for `error`
+    pub async fn insert(&self, source: &str, payload: &[u8], error: &str) -> Result<QuarantinedMessage> {
+        let message = QuarantinedMessage {
+            id: uuid::Uuid::new_v4().to_string(),
+            source: source.to_string(),
+            payload: String::from_utf8_lossy(payload).into_owned(),
+            error: error.to_string(),
+            received_at: Utc::now(),
+        };
+        let row = &message;
+        self.pools
+            .write(|pool| async move {
+                sqlx::query(
+                    "INSERT INTO ingest_quarantine (id, source, payload, error, received_at) VALUES ($1, $2, $3, $4, $5)",
+                )
+                .bind(&row.id)
+                .bind(&row.source)
+                .bind(&row.payload)
+                .bind(&row.error)
+                .bind(row.received_at.timestamp_millis())
+                .execute(&pool)
+                .await
+            })
+            .await
+            .map_err(storage_error)?;
+        Ok(message)
+    }
+
+    /// Newest quarantined messages first, of one source if given, at most
+    /// [`MAX_LISTED`]
+    pub async fn list(&self, source: Option<&str>) -> Result<Vec<QuarantinedMessage>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT id, source, payload, error, received_at FROM ingest_quarantine
+            WHERE $1 IS NULL OR source = $1
+            ORDER BY received_at DESC
+            LIMIT $2
+            "#,
+        )
+        .bind(source)
+        .bind(MAX_LISTED)
+        .fetch_all(self.pools.reader())
+        .await
+        .map_err(storage_error)?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| QuarantinedMessage {
+                id: row.get("id"),
+                source: row.get("source"),
+                payload: row.get("payload"),
+                error: row.get("error"),
+                received_at: Utc.timestamp_millis_opt(row.get("received_at")).single().unwrap_or_default(),
+            })
+            .collect())
+    }
+}
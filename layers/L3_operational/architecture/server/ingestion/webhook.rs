@@ -0,0 +1,151 @@
+//! Inbound webhook deliveries: signature verification and the answers
+//! senders get
+//!
+//! A source with a `timestamp_header` expects the signature scheme of the
+//! server's own outbound webhooks, `sha256=` HMAC over `<timestamp>.<body>`,
+//! and refuses timestamps more than [`TOLERANCE_SECS`] away. Without one the
+//! HMAC is over the body alone, with or without the `sha256=` prefix, as
+//! GitHub and most other senders sign.
+
+use axum::http::{header, HeaderMap, HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use hal9_api_types::{ApiResponse, ErrorCode, ErrorDetails, ErrorResponse};
+
+use super::{Hold, Ingested};
+use crate::webhooks::{self, from_hex};
+
+/// How far a signed timestamp may be from the server's clock
+pub const TOLERANCE_SECS: i64 = 300;
+
+/// Check the signature a delivery carries in `signature_header`, giving
+/// the reason it is refused
+pub fn verify(
+    secret: &str,
+    signature_header: &str,
+    timestamp_header: Option<&str>,
+    headers: &HeaderMap,
+    body: &[u8],
+    now: DateTime<Utc>,
+) -> Result<(), String> {
+    let header_value = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .ok_or_else(|| format!("Missing {} header", name))
+    };
+    let signature = header_value(signature_header)?;
+
+    if let Some(timestamp_header) = timestamp_header {
+        let timestamp: i64 = header_value(timestamp_header)?
+            .parse()
+            .map_err(|_| format!("{} is not a Unix timestamp", timestamp_header))?;
+        if (now.timestamp() - timestamp).abs() > TOLERANCE_SECS {
+            return Err(format!("{} is more than {}s away", timestamp_header, TOLERANCE_SECS));
+        }
+        if !webhooks::verify(secret, timestamp, body, signature) {
+            return Err("Signature does not match".to_string());
+        }
+        return Ok(());
+    }
+
+    let expected = from_hex(signature.strip_prefix("sha256=").unwrap_or(signature))
+        .ok_or_else(|| format!("{} is not a hex HMAC", signature_header))?;
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(body);
+    mac.verify_slice(&expected).map_err(|_| "Signature does not match".to_string())
+}
+
+/// The answer to a delivery: 202 for a chain started, 200 for a duplicate,
+/// 400 for a quarantined message, and 429 or 503 with `Retry-After` for one
+/// that was held and should be sent again
+pub fn response(ingested: Ingested) -> Response {
+    let (status, code, message, details) = match &ingested {
+        Ingested::Accepted { .. } => return (StatusCode::ACCEPTED, Json(ApiResponse::success(ingested))).into_response(),
+        Ingested::Duplicate { .. } => return (StatusCode::OK, Json(ApiResponse::success(ingested))).into_response(),
+        Ingested::Quarantined { quarantine_id, error } => (
+            StatusCode::BAD_REQUEST,
+            ErrorCode::InvalidInput,
+            format!("Message quarantined: {}", error),
+            serde_json::json!({ "quarantine_id": quarantine_id }),
+        ),
+        Ingested::Held { hold } => {
+            let (status, code, message) = match hold {
+                Hold::RateLimited { .. } => {
+                    (StatusCode::TOO_MANY_REQUESTS, ErrorCode::RateLimited, "Source is over its rate limit".to_string())
+                }
+                Hold::OverBudget { spent_usd, budget_usd, .. } => (
+                    StatusCode::TOO_MANY_REQUESTS,
+                    ErrorCode::RateLimited,
+                    format!("Source spent ${:.2} of its ${:.2} budget", spent_usd, budget_usd),
+                ),
+                Hold::Paused => (StatusCode::SERVICE_UNAVAILABLE, ErrorCode::ServiceUnavailable, "Source is paused".to_string()),
+                Hold::ReadOnly => (StatusCode::SERVICE_UNAVAILABLE, ErrorCode::Maintenance, "Server is read-only".to_string()),
+                Hold::Unavailable { error } => (StatusCode::SERVICE_UNAVAILABLE, ErrorCode::ServiceUnavailable, error.clone()),
+            };
+            (status, code, message, serde_json::to_value(hold).unwrap_or_default())
+        }
+    };
+
+    let retry_after = match &ingested {
+        Ingested::Held { hold } => Some(hold.retry_after().as_secs()),
+        _ => None,
+    };
+    let body = ErrorResponse {
+        error: ErrorDetails { code: code.to_string(), message, error_id: None, details: Some(details) },
+        retry_after,
+        help_url: None,
+    };
+    let mut response = (status, Json(body)).into_response();
+    if let Some(secs) = retry_after {
+        response.headers_mut().insert(header::RETRY_AFTER, HeaderValue::from(secs));
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(pairs: &[(&'static str, String)]) -> HeaderMap {
+        pairs.iter().map(|(name, value)| (header::HeaderName::from_static(name), value.parse().unwrap())).collect()
+    }
+
+    #[test]
+    fn test_body_signatures_verify_with_or_without_prefix() {
+        let body = br#"{"title": "Crash"}"#;
+        let mut mac = Hmac::<Sha256>::new_from_slice(b"s3cret").unwrap();
+        mac.update(body);
+        let hex = webhooks::to_hex(&mac.finalize().into_bytes());
+        let now = Utc::now();
+
+        for signature in [format!("sha256={}", hex), hex.clone()] {
+            let headers = headers(&[("x-signature-256", signature)]);
+            assert_eq!(verify("s3cret", "X-Signature-256", None, &headers, body, now), Ok(()));
+            assert!(verify("other", "X-Signature-256", None, &headers, body, now).is_err());
+        }
+        assert!(verify("s3cret", "X-Signature-256", None, &HeaderMap::new(), body, now).is_err());
+    }
+
+    #[test]
+    fn test_timestamped_signatures_expire() {
+        let body = b"{}";
+        let now = Utc::now();
+        let signed = |timestamp: i64| {
+            headers(&[
+                ("x-signature-256", webhooks::sign("s3cret", timestamp, body)),
+                ("x-timestamp", timestamp.to_string()),
+            ])
+        };
+
+        let fresh = signed(now.timestamp());
+        assert_eq!(verify("s3cret", "X-Signature-256", Some("X-Timestamp"), &fresh, body, now), Ok(()));
+        let stale = signed(now.timestamp() - TOLERANCE_SECS - 1);
+        let err = verify("s3cret", "X-Signature-256", Some("X-Timestamp"), &stale, body, now).unwrap_err();
+        assert!(err.contains("300s"), "{}", err);
+    }
+}
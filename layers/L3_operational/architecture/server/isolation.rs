@@ -0,0 +1,538 @@
+//! Process isolation for neurons
+//!
+//! A neuron with `settings.isolation: "process"` keeps its supervisor in
+//! the server and runs its processing loop in a `hal9-neuron-worker` child
+//! process. Parent and worker talk over the worker's stdin and stdout in
+//! the length-prefixed JSON frames peers use:
+//!
+//! 1. The parent sends a [`WorkerInit`] with the server config and the
+//!    neuron's config
+//! 2. The worker builds the neuron as the server would and answers
+//!    [`WorkerReply::Ready`]
+//! 3. Each signal goes down as the same `NetworkMessage::Signal` frame a
+//!    peer server would receive, and comes back as
+//!    [`WorkerReply::Processed`] with the response and what the neuron
+//!    recorded while processing it
+//!
+//! Workers start with a data segment limit (`isolation.max_memory_mb`) and
+//! optionally a CPU time limit, so a runaway neuron fails its allocations
+//! or is killed without taking the server with it. When a worker exits,
+//! the signals it was processing fail and are dead-lettered with reason
+//! [`WORKER_CRASHED`], and the worker is restarted after a backoff that
+//! doubles with each crash until a signal is processed again. Crashes and
+//! restarts are broadcast as [`WorkerEvent`]s.
+//!
+//! Isolated neurons do not use shared memory: the worker builds its neuron
+//! without it.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt};
+use tokio::process::{Child, ChildStdin, ChildStdout, Command};
+use tokio::sync::{broadcast, mpsc, oneshot};
+use tracing::{debug, error, info, warn};
+use uuid::Uuid;
+
+use hal9_core::config::IsolationConfig;
+use hal9_core::secrets::SecretString;
+use hal9_core::{Error, NeuronConfig, NeuronInterface, NeuronSignal, Result, ServerConfig};
+
+use crate::citations::CitedMemory;
+use crate::claude::TokenUsage;
+use crate::concurrency::DeadLetterQueue;
+use crate::cost_tracker::CostTracker;
+use crate::network::protocol::{MessageCodec, NetworkMessage, COMPRESSED_FLAG};
+use crate::output_format::FormatReport;
+use crate::server::HAL9Server;
+use crate::validation::ValidationReport;
+
+/// Executable run for workers when `isolation.worker_command` is unset,
+/// looked up next to the server binary
+pub const WORKER_BINARY: &str = "hal9-neuron-worker";
+
+/// Dead-letter reason of signals lost to a worker exiting
+pub const WORKER_CRASHED: &str = "worker_crashed";
+
+/// Longest a new worker may take to build its neuron
+const WORKER_READY_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Both ends of the pipe are trusted, so frames are bounded only by what
+/// the length prefix can express
+const MAX_WORKER_FRAME_LEN: usize = (COMPRESSED_FLAG - 1) as usize;
+
+/// First frame a worker receives
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WorkerInit {
+    pub config: ServerConfig,
+    /// Secret values by config path; the config itself serializes them
+    /// redacted
+    pub secrets: HashMap<String, String>,
+    pub neuron: NeuronConfig,
+}
+
+/// Frames a worker sends back
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum WorkerReply {
+    /// The neuron is built and accepts signals
+    Ready,
+    Processed(Box<ProcessedSignal>),
+}
+
+/// Outcome of one signal processed in a worker
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProcessedSignal {
+    pub signal_id: Uuid,
+    /// The response, or the error the neuron failed with
+    pub response: std::result::Result<String, String>,
+    pub usage: Option<TokenUsage>,
+    /// Spent on Claude since the worker's previous reply
+    pub cost: f64,
+    pub format_report: Option<FormatReport>,
+    pub validation_report: Option<ValidationReport>,
+    /// Memory entries the response cited
+    #[serde(default)]
+    pub citations: Vec<CitedMemory>,
+}
+
+/// What happened to a worker
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum WorkerEventKind {
+    /// The worker exited; its in-flight signals were dead-lettered
+    Crashed { status: String, lost_signals: usize },
+    /// A replacement worker is up
+    Restarted { pid: u32 },
+}
+
+/// A worker crash or restart, for neuron health events
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkerEvent {
+    pub neuron_id: String,
+    #[serde(flatten)]
+    pub kind: WorkerEventKind,
+    /// Restarts of this neuron's worker so far
+    pub restarts: u32,
+    pub at: DateTime<Utc>,
+}
+
+struct PendingSignal {
+    signal: NeuronSignal,
+    reply: oneshot::Sender<Result<ProcessedSignal>>,
+}
+
+/// Supervisor of one neuron's worker process
+pub struct NeuronWorker {
+    neuron: NeuronConfig,
+    config: ServerConfig,
+    isolation: IsolationConfig,
+    stdin: tokio::sync::Mutex<Option<ChildStdin>>,
+    pending: DashMap<Uuid, PendingSignal>,
+    pid: AtomicU32,
+    up: AtomicBool,
+    stopping: AtomicBool,
+    restarts: AtomicU32,
+    backoff_ms: AtomicU64,
+    last_usage: parking_lot::Mutex<Option<TokenUsage>>,
+    dead_letters: Arc<DeadLetterQueue>,
+    cost_tracker: Arc<CostTracker>,
+    events: broadcast::Sender<WorkerEvent>,
+}
+
+impl NeuronWorker {
+    pub fn new(
+        neuron: NeuronConfig,
+        config: ServerConfig,
+        dead_letters: Arc<DeadLetterQueue>,
+        cost_tracker: Arc<CostTracker>,
+        events: broadcast::Sender<WorkerEvent>,
+    ) -> Self {
+        let isolation = config.isolation.clone();
+        Self {
+            backoff_ms: AtomicU64::new(isolation.restart_initial_backoff_ms),
+            neuron,
+            config,
+            isolation,
+            stdin: tokio::sync::Mutex::new(None),
+            pending: DashMap::new(),
+            pid: AtomicU32::new(0),
+            up: AtomicBool::new(false),
+            stopping: AtomicBool::new(false),
+            restarts: AtomicU32::new(0),
+            last_usage: parking_lot::Mutex::new(None),
+            dead_letters,
+            cost_tracker,
+            events,
+        }
+    }
+
+    /// Start the worker and wait until its neuron is built, then supervise
+    /// it in the background
+    pub async fn start(self: &Arc<Self>) -> Result<()> {
+        self.stopping.store(false, Ordering::SeqCst);
+        let (child, stdout) = self.spawn().await?;
+        info!("Neuron {} running in worker process {}", self.neuron.id, self.pid());
+        tokio::spawn(self.clone().supervise(child, stdout));
+        Ok(())
+    }
+
+    /// Stop the worker by closing its input; it exits once its in-flight
+    /// signals are answered
+    pub async fn shutdown(&self) {
+        self.stopping.store(true, Ordering::SeqCst);
+        self.up.store(false, Ordering::SeqCst);
+        self.stdin.lock().await.take();
+    }
+
+    /// Process id of the running worker, 0 while none is up
+    pub fn pid(&self) -> u32 {
+        self.pid.load(Ordering::SeqCst)
+    }
+
+    /// Whether a worker is up to take signals
+    pub fn is_up(&self) -> bool {
+        self.up.load(Ordering::SeqCst)
+    }
+
+    /// Times the worker was restarted after exiting
+    pub fn restarts(&self) -> u32 {
+        self.restarts.load(Ordering::SeqCst)
+    }
+
+    /// Token usage the worker reported with its latest reply
+    pub fn last_token_usage(&self) -> Option<TokenUsage> {
+        self.last_usage.lock().clone()
+    }
+
+    /// Send a signal to the worker and wait for its outcome
+    pub async fn process(&self, signal: &NeuronSignal) -> Result<ProcessedSignal> {
+        let frame = MessageCodec::encode(&NetworkMessage::Signal(Box::new(signal.clone())))?;
+        let (reply, outcome) = oneshot::channel();
+        self.pending.insert(signal.signal_id, PendingSignal { signal: signal.clone(), reply });
+
+        let written = match self.stdin.lock().await.as_mut() {
+            Some(stdin) => write_frame(stdin, &frame).await,
+            None => {
+                self.pending.remove(&signal.signal_id);
+                return Err(Error::Process(format!("Worker for neuron {} is not running", self.neuron.id)));
+            }
+        };
+        // The pipe only breaks when the worker exits, and its supervisor
+        // then fails and dead-letters the signal
+        if let Err(e) = written {
+            debug!("Sending signal {} to worker for neuron {} failed: {}", signal.signal_id, self.neuron.id, e);
+        }
+
+        let processed = outcome.await
+            .map_err(|_| Error::Process(format!("Worker for neuron {} stopped", self.neuron.id)))??;
+        if processed.cost > 0.0 {
+            let tokens = processed.usage.as_ref().map_or(0, |usage| usage.total_tokens as u64);
+            self.cost_tracker.record_cost(processed.cost, tokens).await;
+        }
+        if processed.usage.is_some() {
+            *self.last_usage.lock() = processed.usage.clone();
+        }
+        Ok(processed)
+    }
+
+    fn worker_command(&self) -> Result<PathBuf> {
+        if let Some(command) = &self.isolation.worker_command {
+            return Ok(PathBuf::from(command));
+        }
+        let exe = std::env::current_exe()
+            .map_err(|e| Error::Process(format!("Cannot locate the server binary: {}", e)))?;
+        Ok(exe.with_file_name(format!("{}{}", WORKER_BINARY, std::env::consts::EXE_SUFFIX)))
+    }
+
+    fn init_frame(&self) -> Result<Vec<u8>> {
+        let mut config = self.config.clone();
+        let secrets = config.secrets_mut()
+            .into_iter()
+            .map(|(path, secret)| (path.to_string(), secret.expose().to_string()))
+            .collect();
+        encode_message(&WorkerInit { config, secrets, neuron: self.neuron.clone() })
+    }
+
+    /// Spawn a worker, hand it its neuron and wait until it is ready
+    async fn spawn(&self) -> Result<(Child, ChildStdout)> {
+        let mut command = Command::new(self.worker_command()?);
+        command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .kill_on_drop(true);
+        #[cfg(unix)]
+        limit_resources(&mut command, &self.isolation);
+
+        let mut child = command.spawn()
+            .map_err(|e| Error::Process(format!("Failed to start worker for neuron {}: {}", self.neuron.id, e)))?;
+        let (mut stdin, mut stdout) = match (child.stdin.take(), child.stdout.take()) {
+            (Some(stdin), Some(stdout)) => (stdin, stdout),
+            _ => return Err(Error::Process(format!("Worker for neuron {} has no pipes", self.neuron.id))),
+        };
+
+        write_frame(&mut stdin, &self.init_frame()?).await?;
+        match tokio::time::timeout(WORKER_READY_TIMEOUT, read_message::<_, WorkerReply>(&mut stdout)).await {
+            Ok(Ok(WorkerReply::Ready)) => {}
+            Ok(Ok(_)) => {
+                return Err(Error::Process(format!("Worker for neuron {} replied before it was ready", self.neuron.id)));
+            }
+            Ok(Err(e)) => {
+                return Err(Error::Process(format!("Worker for neuron {} failed to start: {}", self.neuron.id, e)));
+            }
+            Err(_) => {
+                return Err(Error::Process(format!(
+                    "Worker for neuron {} not ready after {}s", self.neuron.id, WORKER_READY_TIMEOUT.as_secs()
+                )));
+            }
+        }
+
+        *self.stdin.lock().await = Some(stdin);
+        self.pid.store(child.id().unwrap_or(0), Ordering::SeqCst);
+        self.up.store(true, Ordering::SeqCst);
+        Ok((child, stdout))
+    }
+
+    /// Route replies to their signals, and replace the worker whenever it
+    /// exits until shut down
+    async fn supervise(self: Arc<Self>, mut child: Child, mut stdout: ChildStdout) {
+        loop {
+            self.read_replies(&mut stdout).await;
+
+            // The worker closed its output: make sure it is gone
+            let _ = child.start_kill();
+            let status = match child.wait().await {
+                Ok(status) => status.to_string(),
+                Err(e) => e.to_string(),
+            };
+            self.up.store(false, Ordering::SeqCst);
+            self.stdin.lock().await.take();
+            self.pid.store(0, Ordering::SeqCst);
+
+            let lost_signals = self.fail_pending(&status);
+            if self.stopping.load(Ordering::SeqCst) {
+                debug!("Worker for neuron {} stopped: {}", self.neuron.id, status);
+                return;
+            }
+            error!(
+                "Worker for neuron {} exited ({}), dead-lettered {} in-flight signals",
+                self.neuron.id, status, lost_signals
+            );
+            self.emit(WorkerEventKind::Crashed { status, lost_signals });
+
+            loop {
+                let backoff = self.backoff_ms.load(Ordering::SeqCst);
+                self.backoff_ms.store(
+                    (backoff * 2).min(self.isolation.restart_max_backoff_ms),
+                    Ordering::SeqCst,
+                );
+                tokio::time::sleep(Duration::from_millis(backoff)).await;
+                if self.stopping.load(Ordering::SeqCst) {
+                    return;
+                }
+
+                match self.spawn().await {
+                    Ok((new_child, new_stdout)) => {
+                        child = new_child;
+                        stdout = new_stdout;
+                        break;
+                    }
+                    Err(e) => warn!("Restarting worker for neuron {} failed: {}", self.neuron.id, e),
+                }
+            }
+            self.restarts.fetch_add(1, Ordering::SeqCst);
+            info!("Restarted worker for neuron {} as process {}", self.neuron.id, self.pid());
+            self.emit(WorkerEventKind::Restarted { pid: self.pid() });
+        }
+    }
+
+    async fn read_replies(&self, stdout: &mut ChildStdout) {
+        loop {
+            let reply = match read_message::<_, WorkerReply>(stdout).await {
+                Ok(reply) => reply,
+                Err(e) => {
+                    debug!("Worker for neuron {} output closed: {}", self.neuron.id, e);
+                    return;
+                }
+            };
+            let WorkerReply::Processed(processed) = reply else {
+                continue;
+            };
+
+            // A worker that gets signals through is healthy again
+            if processed.response.is_ok() {
+                self.backoff_ms.store(self.isolation.restart_initial_backoff_ms, Ordering::SeqCst);
+            }
+            if let Some((_, pending)) = self.pending.remove(&processed.signal_id) {
+                let _ = pending.reply.send(Ok(*processed));
+            }
+        }
+    }
+
+    /// Fail and dead-letter the signals the exited worker had in flight
+    fn fail_pending(&self, status: &str) -> usize {
+        let signal_ids: Vec<Uuid> = self.pending.iter().map(|entry| *entry.key()).collect();
+        let mut lost = 0;
+        for signal_id in signal_ids {
+            if let Some((_, pending)) = self.pending.remove(&signal_id) {
+                self.dead_letters.push(&self.neuron.id, WORKER_CRASHED, pending.signal);
+                let _ = pending.reply.send(Err(Error::Process(format!(
+                    "Worker for neuron {} exited ({}) while processing the signal", self.neuron.id, status
+                ))));
+                lost += 1;
+            }
+        }
+        lost
+    }
+
+    fn emit(&self, kind: WorkerEventKind) {
+        let _ = self.events.send(WorkerEvent {
+            neuron_id: self.neuron.id.clone(),
+            kind,
+            restarts: self.restarts(),
+            at: Utc::now(),
+        });
+    }
+}
+
+/// Apply the configured memory and CPU limits in the child before it execs
+#[cfg(unix)]
+fn limit_resources(command: &mut Command, isolation: &IsolationConfig) {
+    let memory = isolation.max_memory_mb * 1024 * 1024;
+    let cpu_secs = isolation.max_cpu_secs;
+    // SAFETY: the closure only calls setrlimit, which is async-signal-safe
+    unsafe {
+        command.pre_exec(move || {
+            set_limit(libc::RLIMIT_DATA, memory)?;
+            if let Some(secs) = cpu_secs {
+                set_limit(libc::RLIMIT_CPU, secs)?;
+            }
+            Ok(())
+        });
+    }
+}
+
+#[cfg(all(target_os = "linux", target_env = "gnu"))]
+type Resource = libc::__rlimit_resource_t;
+#[cfg(all(unix, not(all(target_os = "linux", target_env = "gnu"))))]
+type Resource = libc::c_int;
+
+#[cfg(unix)]
+fn set_limit(resource: Resource, value: u64) -> std::io::Result<()> {
+    let limit = libc::rlimit {
+        rlim_cur: value as libc::rlim_t,
+        rlim_max: value as libc::rlim_t,
+    };
+    // SAFETY: `limit` is a valid rlimit for the duration of the call
+    if unsafe { libc::setrlimit(resource, &limit) } != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+fn encode_message<T: Serialize>(message: &T) -> Result<Vec<u8>> {
+    let json = serde_json::to_vec(message)
+        .map_err(|e| Error::Serialization(format!("Failed to encode worker message: {}", e)))?;
+    let mut frame = Vec::with_capacity(4 + json.len());
+    frame.extend_from_slice(&(json.len() as u32).to_be_bytes());
+    frame.extend_from_slice(&json);
+    Ok(frame)
+}
+
+async fn read_message<R: AsyncRead + Unpin, T: DeserializeOwned>(reader: &mut R) -> Result<T> {
+    let frame = MessageCodec::read_frame(reader, MAX_WORKER_FRAME_LEN).await
+        .map_err(|e| Error::Process(format!("Failed to read worker frame: {}", e)))?;
+    serde_json::from_slice(&frame[4..])
+        .map_err(|e| Error::Serialization(format!("Failed to decode worker message: {}", e)))
+}
+
+async fn write_frame<W: AsyncWrite + Unpin>(writer: &mut W, frame: &[u8]) -> Result<()> {
+    writer.write_all(frame).await
+        .map_err(|e| Error::Process(format!("Failed to write worker frame: {}", e)))?;
+    writer.flush().await
+        .map_err(|e| Error::Process(format!("Failed to write worker frame: {}", e)))
+}
+
+/// Serve one neuron over `input` and `output` until the parent closes
+/// `input`. This is the whole of the `hal9-neuron-worker` binary.
+pub async fn run_worker<R, W>(mut input: R, mut output: W) -> Result<()>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin + Send + 'static,
+{
+    let WorkerInit { mut config, secrets, mut neuron } = read_message(&mut input).await?;
+    for (path, secret) in config.secrets_mut() {
+        if let Some(value) = secrets.get(path) {
+            *secret = SecretString::new(value.clone());
+        }
+    }
+    neuron.settings.remove("isolation");
+
+    let server = Arc::new(HAL9Server::new(config));
+    let neuron = Arc::new(server.worker_neuron(neuron)?);
+    neuron.start().await?;
+
+    // Replies are written in the order signals finish
+    let (reply_tx, mut reply_rx) = mpsc::unbounded_channel::<WorkerReply>();
+    let writer = tokio::spawn(async move {
+        while let Some(reply) = reply_rx.recv().await {
+            write_frame(&mut output, &encode_message(&reply)?).await?;
+        }
+        Ok::<_, Error>(())
+    });
+    let _ = reply_tx.send(WorkerReply::Ready);
+
+    let reported_cost = Arc::new(tokio::sync::Mutex::new(0.0));
+    loop {
+        let frame = match MessageCodec::read_frame(&mut input, MAX_WORKER_FRAME_LEN).await {
+            Ok(frame) => frame,
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(Error::Process(format!("Failed to read signal frame: {}", e))),
+        };
+        let signal = match MessageCodec::decode(&frame)? {
+            NetworkMessage::Signal(signal) => signal,
+            _ => {
+                warn!("Worker for neuron {} ignoring a frame that is not a signal", neuron.id);
+                continue;
+            }
+        };
+
+        let neuron = neuron.clone();
+        let server = server.clone();
+        let reply_tx = reply_tx.clone();
+        let reported_cost = reported_cost.clone();
+        tokio::spawn(async move {
+            let response = neuron.process_signal(&signal).await.map_err(|e| e.to_string());
+            let cost = {
+                let mut reported = reported_cost.lock().await;
+                let total = server.cost_stats().await.total_cost;
+                let cost = total - *reported;
+                *reported = total;
+                cost
+            };
+            let _ = reply_tx.send(WorkerReply::Processed(Box::new(ProcessedSignal {
+                signal_id: signal.signal_id,
+                response,
+                usage: neuron.last_token_usage(),
+                cost,
+                format_report: neuron.take_format_report(&signal.signal_id),
+                validation_report: neuron.take_validation_report(&signal.signal_id),
+                citations: neuron.take_citations(&signal.signal_id).unwrap_or_default(),
+            })));
+        });
+    }
+
+    // Answer what is in flight before exiting
+    drop(reply_tx);
+    writer.await
+        .map_err(|e| Error::Process(format!("Worker reply writer failed: {}", e)))??;
+    neuron.shutdown().await
+}
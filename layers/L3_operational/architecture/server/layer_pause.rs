@@ -0,0 +1,312 @@
+//! Operator pauses of whole layers
+//!
+//! While a layer is paused the router holds signals addressed to it instead of
+//! delivering them, up to `layer_pause.max_held` per layer; signals beyond that
+//! go to the dead-letter queue. Resuming sends the held signals back to the
+//! router in the order they arrived. Pauses (but not held signals) are saved to
+//! `layer_pause.state_path`, so a restarted server comes back with the same
+//! layers paused. A pause may carry a deadline after which it lifts itself.
+
+use std::collections::{HashMap, VecDeque};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+use tracing::{error, info, warn};
+
+use hal9_core::config::LayerPauseConfig;
+use hal9_core::NeuronSignal;
+
+use crate::error::{ServerError, ServerResult};
+
+/// Actor recorded when a pause lifts at its deadline
+pub const AUTO_RESUME_ACTOR: &str = "auto-resume";
+
+/// How often pause deadlines are checked
+const AUTO_RESUME_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A paused layer, as saved and reported
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LayerPause {
+    pub layer: String,
+    pub reason: Option<String>,
+    /// Who paused the layer, when known
+    pub paused_by: Option<String>,
+    pub paused_at: DateTime<Utc>,
+    /// When the pause lifts on its own
+    pub resume_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PausedLayerStatus {
+    #[serde(flatten)]
+    pub pause: LayerPause,
+    /// Signals waiting for the layer to resume
+    pub held: usize,
+}
+
+/// What the router should do with a signal
+#[derive(Debug)]
+pub enum LayerAdmission {
+    /// The target layer is not paused
+    Deliver(NeuronSignal),
+    /// Held until the layer resumes
+    Held,
+    /// The layer's hold queue is full; dead-letter the signal
+    Overflow(NeuronSignal),
+}
+
+struct PausedLayer {
+    pause: LayerPause,
+    held: VecDeque<NeuronSignal>,
+}
+
+/// Holds signals for paused layers
+pub struct LayerGate {
+    config: LayerPauseConfig,
+    paused: Mutex<HashMap<String, PausedLayer>>,
+    /// Router queue released signals are sent back to
+    release_tx: Mutex<Option<mpsc::Sender<NeuronSignal>>>,
+    /// Serialises writes of the state file
+    save_lock: Mutex<()>,
+    spilled: AtomicU64,
+}
+
+impl LayerGate {
+    /// A gate with the pauses saved at `config.state_path` restored
+    pub fn load(config: LayerPauseConfig) -> Self {
+        let path = Path::new(&config.state_path);
+        let pauses = if path.exists() {
+            let state = std::fs::read_to_string(path)
+                .map_err(ServerError::from)
+                .and_then(|data| serde_json::from_str::<Vec<LayerPause>>(&data).map_err(ServerError::from));
+            state.unwrap_or_else(|e| {
+                warn!("Ignoring unreadable layer pause state {}: {}", config.state_path, e);
+                Vec::new()
+            })
+        } else {
+            Vec::new()
+        };
+
+        let paused = pauses
+            .into_iter()
+            .map(|pause| {
+                warn!("Layer {} is still paused (since {})", pause.layer, pause.paused_at);
+                let held = VecDeque::new();
+                (pause.layer.clone(), PausedLayer { pause, held })
+            })
+            .collect();
+
+        Self {
+            config,
+            paused: Mutex::new(paused),
+            release_tx: Mutex::new(None),
+            save_lock: Mutex::new(()),
+            spilled: AtomicU64::new(0),
+        }
+    }
+
+    /// Send released signals to the router's queue
+    pub fn attach(&self, release_tx: mpsc::Sender<NeuronSignal>) {
+        *self.release_tx.lock() = Some(release_tx);
+    }
+
+    /// Let a signal through, or hold it if its target layer is paused
+    pub fn admit(&self, signal: NeuronSignal) -> LayerAdmission {
+        let mut paused = self.paused.lock();
+        let Some(layer) = paused.get_mut(&signal.layer_to) else {
+            return LayerAdmission::Deliver(signal);
+        };
+        if layer.held.len() >= self.config.max_held {
+            self.spilled.fetch_add(1, Ordering::Relaxed);
+            return LayerAdmission::Overflow(signal);
+        }
+        layer.held.push_back(signal);
+        LayerAdmission::Held
+    }
+
+    /// Pause a layer, optionally for a limited time. Pausing a paused layer
+    /// replaces its reason and deadline and keeps the signals it holds.
+    pub fn pause(
+        &self,
+        layer: &str,
+        reason: Option<String>,
+        duration: Option<Duration>,
+        actor: Option<&str>,
+    ) -> ServerResult<LayerPause> {
+        let layer = parse_layer(layer)?;
+        let now = Utc::now();
+        let resume_at = match duration {
+            Some(duration) => Some(
+                chrono::Duration::from_std(duration)
+                    .ok()
+                    .and_then(|d| now.checked_add_signed(d))
+                    .ok_or_else(|| ServerError::InvalidInput("Pause duration is too long".to_string()))?,
+            ),
+            None => None,
+        };
+
+        let pause = {
+            let mut paused = self.paused.lock();
+            let entry = paused.entry(layer.clone()).or_insert_with(|| PausedLayer {
+                pause: LayerPause {
+                    layer: layer.clone(),
+                    reason: None,
+                    paused_by: None,
+                    paused_at: now,
+                    resume_at: None,
+                },
+                held: VecDeque::new(),
+            });
+            entry.pause.reason = reason;
+            entry.pause.paused_by = actor.map(str::to_string);
+            entry.pause.resume_at = resume_at;
+            entry.pause.clone()
+        };
+
+        info!(
+            target: "audit",
+            event = "layer_paused",
+            layer = %pause.layer,
+            reason = pause.reason.as_deref().unwrap_or(""),
+            actor = actor.unwrap_or("unknown"),
+            resume_at = ?pause.resume_at,
+            "Layer {} paused", pause.layer
+        );
+        self.persist();
+        Ok(pause)
+    }
+
+    /// Resume a paused layer, sending its held signals back to the router in
+    /// arrival order. Returns how many signals were released.
+    pub async fn resume(&self, layer: &str, actor: Option<&str>) -> ServerResult<usize> {
+        let layer = parse_layer(layer)?;
+        let release_tx = self.release_tx.lock().clone();
+
+        let held = {
+            let mut paused = self.paused.lock();
+            let Some(entry) = paused.get(&layer) else {
+                return Err(ServerError::InvalidInput(format!("Layer {} is not paused", layer)));
+            };
+            if !entry.held.is_empty() && release_tx.is_none() {
+                return Err(ServerError::Internal(format!(
+                    "Layer {} holds signals but no router is attached",
+                    layer
+                )));
+            }
+            paused.remove(&layer).map(|entry| entry.held).unwrap_or_default()
+        };
+
+        let released = held.len();
+        info!(
+            target: "audit",
+            event = "layer_resumed",
+            layer = %layer,
+            actor = actor.unwrap_or("unknown"),
+            released,
+            "Layer {} resumed", layer
+        );
+        self.persist();
+
+        if let Some(release_tx) = release_tx {
+            for signal in held {
+                if release_tx.send(signal).await.is_err() {
+                    error!("Router stopped while releasing signals held for layer {}", layer);
+                    break;
+                }
+            }
+        }
+        Ok(released)
+    }
+
+    /// Resume every layer whose deadline is at or before `now`
+    pub async fn resume_expired(&self, now: DateTime<Utc>) -> Vec<String> {
+        let due: Vec<String> = self
+            .paused
+            .lock()
+            .values()
+            .filter(|p| p.pause.resume_at.is_some_and(|at| at <= now))
+            .map(|p| p.pause.layer.clone())
+            .collect();
+
+        for layer in &due {
+            if let Err(e) = self.resume(layer, Some(AUTO_RESUME_ACTOR)).await {
+                warn!("Failed to auto-resume layer {}: {}", layer, e);
+            }
+        }
+        due
+    }
+
+    /// Lift pauses as their deadlines pass
+    pub fn start(self: &Arc<Self>) {
+        let gate = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(AUTO_RESUME_INTERVAL);
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+            loop {
+                interval.tick().await;
+                gate.resume_expired(Utc::now()).await;
+            }
+        });
+    }
+
+    /// Paused layers, ordered by layer
+    pub fn paused(&self) -> Vec<PausedLayerStatus> {
+        let mut layers: Vec<_> = self
+            .paused
+            .lock()
+            .values()
+            .map(|p| PausedLayerStatus {
+                pause: p.pause.clone(),
+                held: p.held.len(),
+            })
+            .collect();
+        layers.sort_by(|a, b| a.pause.layer.cmp(&b.pause.layer));
+        layers
+    }
+
+    pub fn is_paused(&self, layer: &str) -> bool {
+        self.paused.lock().contains_key(layer)
+    }
+
+    /// Signals dead-lettered because a paused layer's hold queue was full
+    pub fn spilled(&self) -> u64 {
+        self.spilled.load(Ordering::Relaxed)
+    }
+
+    /// Save the pauses, logging rather than failing so an operator's pause
+    /// or resume always takes effect
+    fn persist(&self) {
+        if let Err(e) = self.save() {
+            error!("Failed to save layer pauses to {}: {}", self.config.state_path, e);
+        }
+    }
+
+    fn save(&self) -> ServerResult<()> {
+        let _guard = self.save_lock.lock();
+        let pauses: Vec<LayerPause> = self.paused().into_iter().map(|p| p.pause).collect();
+
+        let path = Path::new(&self.config.state_path);
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+
+        // Write beside the target and rename so a crash never leaves a torn file
+        let tmp = path.with_extension("tmp");
+        std::fs::write(&tmp, serde_json::to_vec_pretty(&pauses)?)?;
+        std::fs::rename(&tmp, path)?;
+        Ok(())
+    }
+}
+
+/// Canonical name of a layer given as e.g. `L4`, `l4` or `strategic`
+fn parse_layer(layer: &str) -> ServerResult<String> {
+    crate::api::parse_layer(layer)
+        .map(str::to_string)
+        .ok_or_else(|| ServerError::InvalidInput(format!("Unknown layer {}", layer)))
+}
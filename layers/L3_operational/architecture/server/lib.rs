@@ -3,34 +3,94 @@
 pub mod api;
 pub mod api_auth;
 pub mod api_codegen;
+pub mod api_mcp;
+pub mod api_orgs;
+pub mod api_webhooks;
+pub mod approvals;
 pub mod auth_middleware;
+pub mod billing_export;
+pub mod budget_period;
 pub mod cache;
+pub mod chain_limits;
+pub mod chain_tracker;
+pub mod chain_compare;
+pub mod chain_visualization;
+pub mod chaos;
+pub mod citations;
 pub mod simple_cache;
 pub mod circuit_breaker;
+pub mod codegen_jobs;
+pub mod concurrency;
+pub mod degraded;
 pub mod claude;
 pub mod claude_enhanced;
 pub mod connection_pool;
+pub mod cost_tags;
 pub mod cost_tracker;
 pub mod database;
 pub mod database_logging;
+pub mod database_migrations;
 pub mod database_runtime;
+pub mod dev;
 // TODO: Fix SQLX Json compatibility issues
 // pub mod enterprise;
 pub mod error;
 pub mod error_recovery;
+pub mod event_stream;
+pub mod fair_scheduler;
+pub mod fan_out;
+pub mod goals;
 pub mod health;
+pub mod idempotency;
+pub mod ingestion;
+pub mod quality;
+pub mod isolation;
+pub mod layer_pause;
+pub mod local_model;
+pub mod log_index;
 pub mod logging;
 pub mod memory_manager;
+pub mod memory_review;
+pub mod memory_summarization;
+pub mod memory_tiering;
 pub mod metrics;
+pub mod mock_scenarios;
+pub mod model_registry;
 pub mod middleware;
 pub mod network;
 pub mod neuron;
+pub mod neuron_changes;
+pub mod org_usage;
+pub mod output_format;
+pub mod pagination;
 pub mod performance;
 pub mod prometheus_exporter;
+pub mod prompt_composition;
 pub mod rate_limiter;
+pub mod read_only;
+pub mod report_privacy;
+pub mod receipts;
+pub mod recovery;
+pub mod retention;
 pub mod router;
 pub mod scaling;
+pub mod self_organizer;
+pub mod sessions;
+pub mod shadow;
+pub mod simulation;
+pub mod singularity;
+pub mod slo;
+pub mod speculation;
+pub mod templates;
+pub mod tenant_encryption;
+pub mod time_travel;
+pub mod timeouts;
+pub mod tool_sandbox;
+pub mod topology;
 pub mod server;
+pub mod validation;
+pub mod warm_pool;
+pub mod webhooks;
 pub mod genius_game;
 pub mod models;
 
@@ -43,6 +103,9 @@ pub mod blockchain;
 #[cfg(feature = "graphql")]
 pub mod api_graphql;
 
+#[cfg(feature = "admin-ui")]
+pub mod admin_ui;
+
 pub use server::HAL9Server;
 pub use claude::{ClaudeInterface, MockClaude, ClaudeAPIClient};
 pub use neuron::{ManagedNeuron, NeuronRegistry};
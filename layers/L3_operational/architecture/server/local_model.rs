@@ -0,0 +1,366 @@
+//! Local model provider with request batching
+//!
+//! Serves layers from a local GPU box running an OpenAI-compatible server.
+//! One request per signal leaves the GPU underused, so requests to the same
+//! model are coalesced into micro-batches: a batch is sent once it holds
+//! `max_batch_size` requests or its first request has waited `max_wait`,
+//! and its completions are fanned back out to the waiting neurons. A request
+//! whose signal is close to its deadline is sent on its own at once.
+//!
+//! Neurons make each provider call within [`with_deadline`], which is how a
+//! signal's deadline reaches the provider behind [`ClaudeInterface`].
+
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use tokio::sync::{mpsc, oneshot};
+use tokio::time::Instant;
+use tracing::debug;
+
+use hal9_core::{config::LocalModelConfig, metadata_schema::keys, Error, NeuronSignal, Result};
+
+use crate::claude::{ClaudeInterface, TokenUsage};
+use crate::metrics::Metrics;
+
+/// Prefix of local models in token usage; they are priced at zero
+pub const LOCAL_MODEL_PREFIX: &str = "local/";
+
+tokio::task_local! {
+    static SIGNAL_DEADLINE: Instant;
+}
+
+/// Run `f` as processing of a signal due by `deadline`
+pub async fn with_deadline<F: Future>(deadline: Instant, f: F) -> F::Output {
+    SIGNAL_DEADLINE.scope(deadline, f).await
+}
+
+/// Deadline of the signal being processed, if any
+pub fn current_deadline() -> Option<Instant> {
+    SIGNAL_DEADLINE.try_with(|deadline| *deadline).ok()
+}
+
+/// When `signal` must be processed by: `timeout` from now, or its
+/// `routing.deadline` if that is sooner
+pub fn signal_deadline(signal: &NeuronSignal, timeout: Duration) -> Instant {
+    let now = Instant::now();
+    let requested = signal
+        .metadata
+        .get(keys::routing::DEADLINE)
+        .and_then(|deadline| DateTime::parse_from_rfc3339(deadline).ok())
+        .map(|deadline| now + (deadline.with_timezone(&Utc) - Utc::now()).to_std().unwrap_or_default());
+    requested.map_or(now + timeout, |requested| requested.min(now + timeout))
+}
+
+/// A completed prompt
+#[derive(Debug, Clone, PartialEq)]
+pub struct LocalCompletion {
+    pub text: String,
+    pub prompt_tokens: u32,
+    pub completion_tokens: u32,
+}
+
+/// A server completing prompts with one model
+#[async_trait]
+pub trait LocalBackend: Send + Sync {
+    /// Complete one prompt
+    async fn complete(&self, prompt: &str) -> Result<LocalCompletion>;
+
+    /// Complete a batch of prompts, in order. Sends one request per prompt,
+    /// in parallel, unless the backend has a batch endpoint.
+    async fn complete_batch(&self, prompts: &[String]) -> Vec<Result<LocalCompletion>> {
+        futures::future::join_all(prompts.iter().map(|prompt| self.complete(prompt))).await
+    }
+}
+
+#[derive(Serialize)]
+struct CompletionRequest<'a> {
+    model: &'a str,
+    prompt: &'a [String],
+    max_tokens: u32,
+}
+
+#[derive(Deserialize)]
+struct CompletionResponse {
+    choices: Vec<CompletionChoice>,
+    usage: Option<CompletionUsage>,
+}
+
+#[derive(Deserialize)]
+struct CompletionChoice {
+    index: usize,
+    text: String,
+}
+
+#[derive(Deserialize)]
+struct CompletionUsage {
+    prompt_tokens: u32,
+    completion_tokens: u32,
+}
+
+/// OpenAI-compatible `/v1/completions` endpoint, as served by vLLM, TGI
+/// or llama.cpp
+pub struct OpenAiCompatibleBackend {
+    client: reqwest::Client,
+    url: String,
+    model: String,
+    max_tokens: u32,
+    batch_endpoint: bool,
+}
+
+impl OpenAiCompatibleBackend {
+    pub fn new(config: &LocalModelConfig) -> Self {
+        Self {
+            client: reqwest::Client::builder()
+                .timeout(Duration::from_secs(config.timeout_secs))
+                .build()
+                .unwrap(),
+            url: format!("{}/v1/completions", config.base_url.trim_end_matches('/')),
+            model: config.model.clone(),
+            max_tokens: config.max_tokens,
+            batch_endpoint: config.batch_endpoint,
+        }
+    }
+
+    /// Complete `prompts` in one request, splitting reported usage evenly
+    async fn request(&self, prompts: &[String]) -> Result<Vec<LocalCompletion>> {
+        let error = |e: String| Error::ClaudeApi(format!("Local model {}: {}", self.model, e));
+        let response = self
+            .client
+            .post(&self.url)
+            .json(&CompletionRequest { model: &self.model, prompt: prompts, max_tokens: self.max_tokens })
+            .send()
+            .await
+            .map_err(|e| error(e.to_string()))?;
+        if !response.status().is_success() {
+            let status = response.status();
+            return Err(error(format!("{} {}", status, response.text().await.unwrap_or_default())));
+        }
+        let mut body: CompletionResponse = response.json().await.map_err(|e| error(e.to_string()))?;
+
+        body.choices.sort_by_key(|choice| choice.index);
+        if body.choices.iter().map(|choice| choice.index).ne(0..prompts.len()) {
+            return Err(error(format!("expected {} choices, one per prompt", prompts.len())));
+        }
+        let share = |tokens: u32| tokens / prompts.len() as u32;
+        let (prompt_tokens, completion_tokens) = body
+            .usage
+            .map_or((0, 0), |usage| (share(usage.prompt_tokens), share(usage.completion_tokens)));
+        Ok(body
+            .choices
+            .into_iter()
+            .map(|choice| LocalCompletion { text: choice.text, prompt_tokens, completion_tokens })
+            .collect())
+    }
+}
+
+#[async_trait]
+impl LocalBackend for OpenAiCompatibleBackend {
+    async fn complete(&self, prompt: &str) -> Result<LocalCompletion> {
+        let mut completions = self.request(&[prompt.to_string()]).await?;
+        Ok(completions.remove(0))
+    }
+
+    async fn complete_batch(&self, prompts: &[String]) -> Vec<Result<LocalCompletion>> {
+        if !self.batch_endpoint {
+            return futures::future::join_all(prompts.iter().map(|prompt| self.complete(prompt))).await;
+        }
+        match self.request(prompts).await {
+            Ok(completions) => completions.into_iter().map(Ok).collect(),
+            Err(e) => prompts.iter().map(|_| Err(Error::ClaudeApi(e.to_string()))).collect(),
+        }
+    }
+}
+
+/// A request waiting for its batch
+struct Queued {
+    prompt: String,
+    enqueued: Instant,
+    reply: oneshot::Sender<Result<LocalCompletion>>,
+}
+
+/// Coalesces requests to one local model into batches
+pub struct LocalModelBatcher {
+    model: String,
+    backend: Arc<dyn LocalBackend>,
+    queue: mpsc::UnboundedSender<Queued>,
+    max_wait: Duration,
+    deadline_margin: Duration,
+    metrics: Option<Arc<Metrics>>,
+}
+
+impl LocalModelBatcher {
+    /// Start batching requests to `backend`. Must be called within a tokio
+    /// runtime.
+    pub fn new(
+        model: impl Into<String>,
+        backend: Arc<dyn LocalBackend>,
+        max_batch_size: usize,
+        max_wait: Duration,
+        deadline_margin: Duration,
+        metrics: Option<Arc<Metrics>>,
+    ) -> Arc<Self> {
+        let model = model.into();
+        let (queue, queued) = mpsc::unbounded_channel();
+        tokio::spawn(batch_loop(
+            queued,
+            backend.clone(),
+            model.clone(),
+            max_batch_size.max(1),
+            max_wait,
+            metrics.clone(),
+        ));
+        Arc::new(Self { model, backend, queue, max_wait, deadline_margin, metrics })
+    }
+
+    pub fn from_config(config: &LocalModelConfig, metrics: Option<Arc<Metrics>>) -> Arc<Self> {
+        Self::new(
+            config.model.clone(),
+            Arc::new(OpenAiCompatibleBackend::new(config)),
+            config.max_batch_size,
+            Duration::from_millis(config.max_wait_ms),
+            Duration::from_millis(config.deadline_margin_ms),
+            metrics,
+        )
+    }
+
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+
+    /// Complete `prompt` with the next batch, or on its own at once if
+    /// `deadline` is too close to wait for one
+    pub async fn submit(&self, prompt: String, deadline: Option<Instant>) -> Result<LocalCompletion> {
+        let now = Instant::now();
+        if deadline.is_some_and(|deadline| deadline <= now + self.max_wait + self.deadline_margin) {
+            debug!("Local model {} request bypasses batching, deadline near", self.model);
+            if let Some(metrics) = &self.metrics {
+                metrics.record_local_bypass(&self.model);
+            }
+            return self.backend.complete(&prompt).await;
+        }
+
+        let (reply, completion) = oneshot::channel();
+        self.queue
+            .send(Queued { prompt, enqueued: now, reply })
+            .map_err(|_| Error::ClaudeApi(format!("Local model {} batcher stopped", self.model)))?;
+        completion
+            .await
+            .map_err(|_| Error::ClaudeApi(format!("Local model {} dropped the request", self.model)))?
+    }
+}
+
+/// Form batches from `queued` until every sender is gone
+async fn batch_loop(
+    mut queued: mpsc::UnboundedReceiver<Queued>,
+    backend: Arc<dyn LocalBackend>,
+    model: String,
+    max_batch_size: usize,
+    max_wait: Duration,
+    metrics: Option<Arc<Metrics>>,
+) {
+    while let Some(first) = queued.recv().await {
+        let send_at = first.enqueued + max_wait;
+        let mut batch = vec![first];
+        while batch.len() < max_batch_size {
+            tokio::select! {
+                next = queued.recv() => match next {
+                    Some(next) => batch.push(next),
+                    None => break,
+                },
+                _ = tokio::time::sleep_until(send_at) => break,
+            }
+        }
+        tokio::spawn(dispatch(batch, backend.clone(), model.clone(), metrics.clone()));
+    }
+}
+
+/// Send a batch and hand each completion to its requester
+async fn dispatch(batch: Vec<Queued>, backend: Arc<dyn LocalBackend>, model: String, metrics: Option<Arc<Metrics>>) {
+    let sent = Instant::now();
+    if let Some(metrics) = &metrics {
+        metrics.record_local_batch(&model, batch.len());
+        for queued in &batch {
+            metrics.record_local_queue_latency(&model, sent.saturating_duration_since(queued.enqueued));
+        }
+    }
+
+    let (prompts, replies): (Vec<_>, Vec<_>) = batch.into_iter().map(|queued| (queued.prompt, queued.reply)).unzip();
+    let mut completions = backend.complete_batch(&prompts).await;
+    if completions.len() != replies.len() {
+        let error = format!("Local model {} answered {} of {} prompts", model, completions.len(), replies.len());
+        completions = replies.iter().map(|_| Err(Error::ClaudeApi(error.clone()))).collect();
+    }
+    for (reply, completion) in replies.into_iter().zip(completions) {
+        // The requester may have timed out
+        let _ = reply.send(completion);
+    }
+}
+
+/// Batchers shared by every neuron using the same server and model. The
+/// first configuration seen for a server and model sets its batching.
+#[derive(Default)]
+pub struct LocalModels {
+    batchers: DashMap<(String, String), Arc<LocalModelBatcher>>,
+}
+
+impl LocalModels {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Batcher for `config`'s server and model, started on first use
+    pub fn batcher(&self, config: &LocalModelConfig, metrics: &Arc<Metrics>) -> Arc<LocalModelBatcher> {
+        self.batchers
+            .entry((config.base_url.clone(), config.model.clone()))
+            .or_insert_with(|| LocalModelBatcher::from_config(config, Some(metrics.clone())))
+            .clone()
+    }
+}
+
+/// A layer's provider backed by a batched local model
+pub struct LocalModelClaude {
+    batcher: Arc<LocalModelBatcher>,
+    system_prompt: String,
+    last_usage: Mutex<Option<TokenUsage>>,
+}
+
+impl LocalModelClaude {
+    pub fn new(batcher: Arc<LocalModelBatcher>, layer: &str) -> Self {
+        Self {
+            batcher,
+            system_prompt: hal9_core::config::get_system_prompt(layer),
+            last_usage: Mutex::new(None),
+        }
+    }
+}
+
+#[async_trait]
+impl ClaudeInterface for LocalModelClaude {
+    async fn send_message(&self, message: &str) -> Result<String> {
+        let prompt = format!("{}\n\n{}", self.system_prompt, message);
+        let completion = self.batcher.submit(prompt, current_deadline()).await?;
+        if let Ok(mut last_usage) = self.last_usage.lock() {
+            last_usage.replace(TokenUsage {
+                prompt_tokens: completion.prompt_tokens,
+                completion_tokens: completion.completion_tokens,
+                total_tokens: completion.prompt_tokens + completion.completion_tokens,
+                model: Some(format!("{}{}", LOCAL_MODEL_PREFIX, self.batcher.model())),
+                remapped_from: None,
+            });
+        }
+        Ok(completion.text)
+    }
+
+    fn system_prompt(&self) -> &str {
+        &self.system_prompt
+    }
+
+    fn last_token_usage(&self) -> Option<TokenUsage> {
+        self.last_usage.lock().ok()?.clone()
+    }
+}
@@ -0,0 +1,393 @@
+//! Bounded in-memory index of recent log entries
+//!
+//! A tracing layer copies every event that passes the active filter into a
+//! ring buffer, lifting the correlation fields (chain, signal, neuron, layer,
+//! event) out of the event and its enclosing spans so a chain's logs can be
+//! queried without external infrastructure.
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::time::Instant;
+
+use chrono::{DateTime, Duration, Utc};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use tracing::field::{Field, Visit};
+use tracing::{span, Event, Level, Subscriber};
+use tracing_subscriber::{layer::Context, registry::LookupSpan, Layer};
+use hal9_core::config::LogIndexConfig;
+
+/// Fields lifted out of events and spans into dedicated entry fields
+pub const CORRELATION_FIELDS: [&str; 5] = ["chain_id", "signal_id", "neuron_id", "layer", "event"];
+
+/// Longest message kept per entry, in bytes
+const MAX_MESSAGE_LEN: usize = 2048;
+/// Most extra fields kept per entry
+const MAX_FIELDS: usize = 16;
+/// Longest extra field value kept, in bytes
+const MAX_FIELD_LEN: usize = 256;
+
+/// One indexed log line
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogEntry {
+    pub timestamp: DateTime<Utc>,
+    pub level: String,
+    pub target: String,
+    pub message: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chain_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signal_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub neuron_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub layer: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub event: Option<String>,
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    pub fields: Map<String, Value>,
+}
+
+impl LogEntry {
+    fn set_correlation(&mut self, name: &str, value: String) {
+        let slot = match name {
+            "chain_id" => &mut self.chain_id,
+            "signal_id" => &mut self.signal_id,
+            "neuron_id" => &mut self.neuron_id,
+            "layer" => &mut self.layer,
+            "event" => &mut self.event,
+            _ => return,
+        };
+        *slot = Some(value);
+    }
+
+    /// Severity, for "at least this severe" filtering
+    fn severity(&self) -> Level {
+        self.level.parse().unwrap_or(Level::TRACE)
+    }
+}
+
+/// Filters for `LogIndex::query`
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct LogQuery {
+    pub chain_id: Option<String>,
+    pub signal_id: Option<String>,
+    pub neuron_id: Option<String>,
+    /// Minimum severity, e.g. "warn" also matches errors
+    pub level: Option<String>,
+    /// RFC 3339 timestamp, or a relative window such as "30m" or "2h"
+    pub since: Option<String>,
+    /// Newest entries returned at most
+    pub limit: Option<usize>,
+}
+
+/// Default number of entries returned by a query
+pub const DEFAULT_QUERY_LIMIT: usize = 200;
+
+/// Parse `since` as an RFC 3339 timestamp or a relative "<n>s|m|h|d" window
+pub fn parse_since(since: &str, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    if let Ok(at) = DateTime::parse_from_rfc3339(since) {
+        return Some(at.with_timezone(&Utc));
+    }
+
+    let since = since.trim();
+    let unit = since.chars().last()?;
+    let amount: i64 = since[..since.len() - unit.len_utf8()].parse().ok()?;
+    let window = match unit {
+        's' => Duration::seconds(amount),
+        'm' => Duration::minutes(amount),
+        'h' => Duration::hours(amount),
+        'd' => Duration::days(amount),
+        _ => return None,
+    };
+    Some(now - window)
+}
+
+/// Indexing counters, including the time spent indexing
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogIndexStats {
+    pub enabled: bool,
+    pub entries: usize,
+    pub capacity: usize,
+    pub retention_hours: u64,
+    pub indexed_total: u64,
+    pub evicted_total: u64,
+    /// Mean time spent capturing and storing one entry
+    pub avg_index_ns: u64,
+}
+
+/// Ring buffer of recent log entries, bounded by count and age
+pub struct LogIndex {
+    enabled: AtomicBool,
+    capacity: AtomicUsize,
+    retention_hours: AtomicU64,
+    entries: Mutex<VecDeque<LogEntry>>,
+    indexed_total: AtomicU64,
+    evicted_total: AtomicU64,
+    index_nanos_total: AtomicU64,
+}
+
+impl LogIndex {
+    pub fn new(config: &LogIndexConfig) -> Self {
+        Self {
+            enabled: AtomicBool::new(config.enabled),
+            capacity: AtomicUsize::new(config.capacity),
+            retention_hours: AtomicU64::new(config.retention_hours),
+            entries: Mutex::new(VecDeque::new()),
+            indexed_total: AtomicU64::new(0),
+            evicted_total: AtomicU64::new(0),
+            index_nanos_total: AtomicU64::new(0),
+        }
+    }
+
+    /// Apply new limits; shrinking evicts the oldest entries immediately
+    pub fn configure(&self, config: &LogIndexConfig) {
+        self.enabled.store(config.enabled, Ordering::Relaxed);
+        self.capacity.store(config.capacity, Ordering::Relaxed);
+        self.retention_hours.store(config.retention_hours, Ordering::Relaxed);
+
+        let mut entries = self.entries.lock();
+        if !config.enabled {
+            entries.clear();
+        }
+        self.evict(&mut entries, Utc::now());
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    /// Tracing layer feeding this index
+    pub fn layer(self: &std::sync::Arc<Self>) -> LogIndexLayer {
+        LogIndexLayer { index: self.clone() }
+    }
+
+    pub fn push(&self, entry: LogEntry) {
+        if !self.is_enabled() {
+            return;
+        }
+        let now = entry.timestamp;
+        let mut entries = self.entries.lock();
+        entries.push_back(entry);
+        self.evict(&mut entries, now);
+        self.indexed_total.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn evict(&self, entries: &mut VecDeque<LogEntry>, now: DateTime<Utc>) {
+        let capacity = self.capacity.load(Ordering::Relaxed);
+        let cutoff = now - Duration::hours(self.retention_hours.load(Ordering::Relaxed) as i64);
+
+        let mut evicted = 0;
+        while entries.len() > capacity
+            || entries.front().map(|e| e.timestamp < cutoff).unwrap_or(false)
+        {
+            entries.pop_front();
+            evicted += 1;
+        }
+        if evicted > 0 {
+            self.evicted_total.fetch_add(evicted, Ordering::Relaxed);
+        }
+    }
+
+    /// Matching entries, oldest first, limited to the newest `limit`
+    pub fn query(&self, query: &LogQuery) -> Vec<LogEntry> {
+        let min_level = query.level.as_deref().and_then(|l| l.parse::<Level>().ok());
+        let since = query.since.as_deref().and_then(|s| parse_since(s, Utc::now()));
+        let limit = query.limit.unwrap_or(DEFAULT_QUERY_LIMIT);
+
+        let matches = |e: &LogEntry| {
+            query.chain_id.as_ref().is_none_or(|id| e.chain_id.as_ref() == Some(id))
+                && query.signal_id.as_ref().is_none_or(|id| e.signal_id.as_ref() == Some(id))
+                && query.neuron_id.as_ref().is_none_or(|id| e.neuron_id.as_ref() == Some(id))
+                && min_level.is_none_or(|level| e.severity() <= level)
+                && since.is_none_or(|since| e.timestamp >= since)
+        };
+
+        let entries = self.entries.lock();
+        let mut result: Vec<LogEntry> = entries.iter().rev()
+            .filter(|e| matches(e))
+            .take(limit)
+            .cloned()
+            .collect();
+        result.reverse();
+        result
+    }
+
+    fn record_overhead(&self, nanos: u64) {
+        self.index_nanos_total.fetch_add(nanos, Ordering::Relaxed);
+    }
+
+    pub fn stats(&self) -> LogIndexStats {
+        let indexed_total = self.indexed_total.load(Ordering::Relaxed);
+        LogIndexStats {
+            enabled: self.is_enabled(),
+            entries: self.entries.lock().len(),
+            capacity: self.capacity.load(Ordering::Relaxed),
+            retention_hours: self.retention_hours.load(Ordering::Relaxed),
+            indexed_total,
+            evicted_total: self.evicted_total.load(Ordering::Relaxed),
+            avg_index_ns: self.index_nanos_total.load(Ordering::Relaxed)
+                .checked_div(indexed_total)
+                .unwrap_or(0),
+        }
+    }
+}
+
+/// Correlation fields recorded on a span, inherited by events inside it
+struct SpanCorrelation(Vec<(&'static str, String)>);
+
+/// Collects event or span fields, truncating to keep entries bounded
+#[derive(Default)]
+struct FieldCollector {
+    message: Option<String>,
+    correlation: Vec<(&'static str, String)>,
+    fields: Map<String, Value>,
+}
+
+impl FieldCollector {
+    fn insert(&mut self, field: &Field, value: Value) {
+        let name = field.name();
+        if name == "message" {
+            let text = match value {
+                Value::String(s) => s,
+                other => other.to_string(),
+            };
+            self.message = Some(truncate(text, MAX_MESSAGE_LEN));
+        } else if CORRELATION_FIELDS.contains(&name) {
+            let text = match value {
+                Value::String(s) => s,
+                other => other.to_string(),
+            };
+            self.correlation.push((name, truncate(text, MAX_FIELD_LEN)));
+        } else if self.fields.len() < MAX_FIELDS {
+            let value = match value {
+                Value::String(s) => Value::String(truncate(s, MAX_FIELD_LEN)),
+                other => other,
+            };
+            self.fields.insert(name.to_string(), value);
+        }
+    }
+}
+
+impl Visit for FieldCollector {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.insert(field, Value::String(value.to_string()));
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.insert(field, Value::from(value));
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.insert(field, Value::from(value));
+    }
+
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.insert(field, Value::from(value));
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.insert(field, Value::from(value));
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.insert(field, Value::String(format!("{:?}", value)));
+    }
+}
+
+fn truncate(mut text: String, max: usize) -> String {
+    if text.len() > max {
+        let mut end = max;
+        while !text.is_char_boundary(end) {
+            end -= 1;
+        }
+        text.truncate(end);
+    }
+    text
+}
+
+/// Tracing layer that copies events into a `LogIndex`
+pub struct LogIndexLayer {
+    index: std::sync::Arc<LogIndex>,
+}
+
+impl<S> Layer<S> for LogIndexLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &span::Attributes<'_>, id: &span::Id, ctx: Context<'_, S>) {
+        let mut collector = FieldCollector::default();
+        attrs.record(&mut collector);
+        if collector.correlation.is_empty() {
+            return;
+        }
+        if let Some(span) = ctx.span(id) {
+            span.extensions_mut().insert(SpanCorrelation(collector.correlation));
+        }
+    }
+
+    fn on_record(&self, id: &span::Id, values: &span::Record<'_>, ctx: Context<'_, S>) {
+        let mut collector = FieldCollector::default();
+        values.record(&mut collector);
+        if collector.correlation.is_empty() {
+            return;
+        }
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+
+        let mut extensions = span.extensions_mut();
+        match extensions.get_mut::<SpanCorrelation>() {
+            Some(existing) => {
+                for (name, value) in collector.correlation {
+                    existing.0.retain(|(n, _)| *n != name);
+                    existing.0.push((name, value));
+                }
+            }
+            None => extensions.insert(SpanCorrelation(collector.correlation)),
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        if !self.index.is_enabled() {
+            return;
+        }
+        let started = Instant::now();
+
+        let mut collector = FieldCollector::default();
+        event.record(&mut collector);
+
+        let metadata = event.metadata();
+        let mut entry = LogEntry {
+            timestamp: Utc::now(),
+            level: metadata.level().to_string(),
+            target: metadata.target().to_string(),
+            message: collector.message.unwrap_or_default(),
+            chain_id: None,
+            signal_id: None,
+            neuron_id: None,
+            layer: None,
+            event: None,
+            fields: collector.fields,
+        };
+
+        // Outer spans first so inner spans and the event itself take precedence
+        if let Some(scope) = ctx.event_scope(event) {
+            for span in scope.from_root() {
+                if let Some(correlation) = span.extensions().get::<SpanCorrelation>() {
+                    for (name, value) in &correlation.0 {
+                        entry.set_correlation(name, value.clone());
+                    }
+                }
+            }
+        }
+        for (name, value) in collector.correlation {
+            entry.set_correlation(name, value);
+        }
+
+        self.index.push(entry);
+        self.index.record_overhead(started.elapsed().as_nanos() as u64);
+    }
+}
@@ -3,17 +3,132 @@
 //! This module provides structured logging with consistent formats,
 //! performance metrics, and request/response tracing.
 
+use std::collections::BTreeMap;
+use std::sync::{Arc, OnceLock};
 use std::time::Duration;
+use parking_lot::Mutex;
 use tracing::Span;
 use tracing_subscriber::{
+    filter::LevelFilter,
     fmt::{self, format::FmtSpan},
     layer::SubscriberExt,
+    reload,
     util::SubscriberInitExt,
-    EnvFilter,
+    EnvFilter, Registry,
 };
 use uuid::Uuid;
 use chrono::{DateTime, Utc};
 use serde::Serialize;
+use hal9_core::{NeuronSignal, config::LogIndexConfig};
+use crate::chain_tracker::CHAIN_ID_KEY;
+use crate::log_index::{LogIndex, LogIndexLayer};
+
+static LOG_INDEX: OnceLock<Arc<LogIndex>> = OnceLock::new();
+static LOG_LEVELS: OnceLock<LogLevelControl> = OnceLock::new();
+
+/// Index of recent log entries, once logging is initialized
+pub fn log_index() -> Option<&'static Arc<LogIndex>> {
+    LOG_INDEX.get()
+}
+
+/// Runtime log level control, once logging is initialized
+pub fn log_levels() -> Option<&'static LogLevelControl> {
+    LOG_LEVELS.get()
+}
+
+/// Reloadable filter plus log index layer shared by every init function
+fn install_controls(default_directives: &str) -> (reload::Layer<EnvFilter, Registry>, LogIndexLayer) {
+    let base = std::env::var(EnvFilter::DEFAULT_ENV)
+        .ok()
+        .filter(|directives| EnvFilter::try_new(directives).is_ok())
+        .unwrap_or_else(|| default_directives.to_string());
+    let (filter, control) = LogLevelControl::new(base);
+    let index = Arc::new(LogIndex::new(&LogIndexConfig::default()));
+    let index_layer = index.layer();
+
+    let _ = LOG_LEVELS.set(control);
+    let _ = LOG_INDEX.set(index);
+    (filter, index_layer)
+}
+
+/// Log levels as currently applied
+#[derive(Debug, Clone, Serialize)]
+pub struct LogLevels {
+    /// Directives from `RUST_LOG`, the built-in default, or the
+    /// `monitoring.log_level` runtime override
+    pub base: String,
+    /// Per-module levels set at runtime
+    pub overrides: BTreeMap<String, String>,
+}
+
+/// Adjusts per-module log levels on a running subscriber
+pub struct LogLevelControl {
+    handle: reload::Handle<EnvFilter, Registry>,
+    /// Base directives at startup
+    initial: String,
+    base: Mutex<String>,
+    overrides: Mutex<BTreeMap<String, String>>,
+}
+
+impl LogLevelControl {
+    /// Create a control and the filter layer it drives. `base` must be
+    /// valid `EnvFilter` directives.
+    pub fn new(base: impl Into<String>) -> (reload::Layer<EnvFilter, Registry>, Self) {
+        let base = base.into();
+        let (filter, handle) = reload::Layer::new(EnvFilter::new(&base));
+        (filter, Self { handle, initial: base.clone(), base: Mutex::new(base), overrides: Mutex::new(BTreeMap::new()) })
+    }
+
+    /// Replace the base directives, keeping per-module overrides. `None`
+    /// restores the directives from startup.
+    pub fn set_base(&self, directives: Option<&str>) -> Result<(), String> {
+        let directives = directives.unwrap_or(&self.initial);
+        EnvFilter::try_new(directives).map_err(|e| format!("Invalid log directives '{}': {}", directives, e))?;
+
+        let overrides = self.overrides.lock();
+        *self.base.lock() = directives.to_string();
+        self.apply(&overrides)
+    }
+
+    /// Set the level for a module path such as `hal9_server::router`
+    pub fn set_level(&self, module: &str, level: &str) -> Result<(), String> {
+        if module.is_empty() || !module.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == ':') {
+            return Err(format!("Invalid module path '{}'", module));
+        }
+        let level: LevelFilter = level.parse()
+            .map_err(|_| format!("Invalid log level '{}'", level))?;
+
+        let mut overrides = self.overrides.lock();
+        overrides.insert(module.to_string(), level.to_string().to_lowercase());
+        self.apply(&overrides)
+    }
+
+    /// Drop a runtime override, returning the module to the base directives
+    pub fn clear_level(&self, module: &str) -> Result<bool, String> {
+        let mut overrides = self.overrides.lock();
+        let removed = overrides.remove(module).is_some();
+        if removed {
+            self.apply(&overrides)?;
+        }
+        Ok(removed)
+    }
+
+    pub fn levels(&self) -> LogLevels {
+        LogLevels {
+            base: self.base.lock().clone(),
+            overrides: self.overrides.lock().clone(),
+        }
+    }
+
+    fn apply(&self, overrides: &BTreeMap<String, String>) -> Result<(), String> {
+        let mut directives = self.base.lock().clone();
+        for (module, level) in overrides {
+            directives.push_str(&format!(",{}={}", module, level));
+        }
+        let filter = EnvFilter::try_new(&directives).map_err(|e| e.to_string())?;
+        self.handle.reload(filter).map_err(|e| e.to_string())
+    }
+}
 
 /// Structured log format for consistent logging across all components
 #[derive(Debug, Clone, Serialize)]
@@ -37,11 +152,10 @@ pub struct StructuredLog {
 
 /// Initialize the global logging subscriber with structured JSON output
 pub fn init_structured_logging() {
-    let env_filter = EnvFilter::try_from_default_env()
-        .unwrap_or_else(|_| {
-            // Default log levels per module
-            "info,hal9=debug,hal9_core=debug,hal9_server=debug,tower_http=debug".into()
-        });
+    // Default log levels per module
+    let (env_filter, index_layer) = install_controls(
+        "info,hal9=debug,hal9_core=debug,hal9_server=debug,tower_http=debug",
+    );
 
     let fmt_layer = fmt::layer()
         .json()
@@ -57,15 +171,15 @@ pub fn init_structured_logging() {
     tracing_subscriber::registry()
         .with(env_filter)
         .with(fmt_layer)
+        .with(index_layer)
         .init();
 }
 
 /// Initialize the global logging subscriber with pretty human-readable output
 pub fn init_pretty_logging() {
-    let env_filter = EnvFilter::try_from_default_env()
-        .unwrap_or_else(|_| {
-            "info,hal9=debug,hal9_core=debug,hal9_server=debug,tower_http=debug".into()
-        });
+    let (env_filter, index_layer) = install_controls(
+        "info,hal9=debug,hal9_core=debug,hal9_server=debug,tower_http=debug",
+    );
 
     let fmt_layer = fmt::layer()
         .pretty()
@@ -78,6 +192,26 @@ pub fn init_pretty_logging() {
     tracing_subscriber::registry()
         .with(env_filter)
         .with(fmt_layer)
+        .with(index_layer)
+        .init();
+}
+
+/// Initialize logging to stderr, keeping stdout free for protocol traffic
+/// (used by the MCP stdio transport)
+pub fn init_stderr_logging() {
+    let (env_filter, index_layer) = install_controls(
+        "info,hal9=debug,hal9_core=debug,hal9_server=debug",
+    );
+
+    let fmt_layer = fmt::layer()
+        .with_writer(std::io::stderr)
+        .with_ansi(false)
+        .with_target(true);
+
+    tracing_subscriber::registry()
+        .with(env_filter)
+        .with(fmt_layer)
+        .with(index_layer)
         .init();
 }
 
@@ -210,6 +344,34 @@ pub fn neuron_span(neuron_id: &str, layer: &str, operation: &str) -> Span {
     )
 }
 
+/// Create a span for a call into a plugin export. Entered inside a neuron's
+/// processing span, so chains show the time spent in plugins; `duration_ms`
+/// is recorded when the call returns.
+pub fn plugin_span(plugin: &str, function: &str) -> Span {
+    tracing::info_span!(
+        "plugin_call",
+        plugin = %plugin,
+        function = %function,
+        duration_ms = tracing::field::Empty,
+        outcome = tracing::field::Empty
+    )
+}
+
+/// Create a span carrying the correlation fields for processing one signal
+pub fn signal_span(signal: &NeuronSignal) -> Span {
+    let span = tracing::info_span!(
+        "signal",
+        chain_id = tracing::field::Empty,
+        signal_id = %signal.signal_id,
+        neuron_id = %signal.to_neuron,
+        layer = %signal.layer_to
+    );
+    if let Some(chain_id) = signal.metadata.get(CHAIN_ID_KEY) {
+        span.record("chain_id", chain_id.as_str());
+    }
+    span
+}
+
 /// Create a new span for an API request
 pub fn api_span(method: &str, path: &str, trace_id: &str) -> Span {
     tracing::info_span!(
@@ -2,20 +2,35 @@
 
 use std::sync::Arc;
 use anyhow::Result;
-use tracing::{info, error};
+use tracing::{info, warn, error};
 use tokio::signal;
 
 use hal9_core::ServerConfig;
+use hal9_core::config_layers::LayeredConfig;
+use hal9_core::secrets::EnvKeyProvider;
+use hal9_core::sqlite::{SqlitePools, SqliteTuning};
 
 // For binaries in the same crate, we need to use the library crate name
 extern crate hal9_server;
 
-use hal9_server::{HAL9Server, api, logging, error_recovery};
+use hal9_server::{HAL9Server, api, api_mcp, database_migrations, dev, logging, error_recovery};
+use hal9_server::scaling::DrainOutcome;
+
+/// Flag selecting the MCP stdio transport instead of the HTTP server
+const MCP_STDIO_FLAG: &str = "--mcp-stdio";
+
+/// Flag applying pending database migrations and exiting, for init containers
+const MIGRATE_ONLY_FLAG: &str = "--migrate-only";
 
 #[tokio::main]
 async fn main() -> Result<()> {
+    let mcp_stdio = std::env::args().any(|arg| arg == MCP_STDIO_FLAG);
+    
     // Initialize structured logging based on environment
-    if std::env::var("LOG_FORMAT").unwrap_or_default() == "json" {
+    if mcp_stdio {
+        // stdout carries MCP messages
+        logging::init_stderr_logging();
+    } else if std::env::var("LOG_FORMAT").unwrap_or_default() == "json" {
         logging::init_structured_logging();
     } else {
         logging::init_pretty_logging();
@@ -26,19 +41,26 @@ async fn main() -> Result<()> {
     
     info!("Starting 2HAL9 server v{}", env!("CARGO_PKG_VERSION"));
     
-    // Load configuration
-    let config = load_config().await?;
+    // Load configuration: defaults, then the file, then HAL9__ variables
+    let layers = load_config().await?;
+    let config = layers.config().clone();
+    
+    if std::env::args().any(|arg| arg == MIGRATE_ONLY_FLAG) {
+        return migrate_only(&config).await;
+    }
     
     // Create server
-    let mut server = HAL9Server::new(config.clone());
+    let mut server = HAL9Server::with_config_layers(layers);
     
     // Initialize auth if enabled
     if config.auth.enabled {
-        let auth_pool = sqlx::SqlitePool::connect(&format!("sqlite:{}?mode=rwc", config.auth.database_path))
+        let auth_url = format!("sqlite:{}?mode=rwc", config.auth.database_path);
+        let auth_pools = SqlitePools::connect(&auth_url, SqliteTuning::default())
             .await
             .map_err(|e| anyhow::anyhow!("Failed to connect to auth database: {}", e))?;
+        auth_pools.quick_check(&config.auth.database_path).await?;
         
-        server.initialize_auth(auth_pool).await?;
+        server.initialize_auth(auth_pools).await?;
     }
     
     let server = Arc::new(server);
@@ -46,6 +68,15 @@ async fn main() -> Result<()> {
     // Start the server
     server.start().await?;
     
+    // Serve MCP over stdio and exit when the client disconnects
+    if mcp_stdio {
+        info!("Serving MCP over stdio");
+        let mcp_state = api_mcp::McpState::new(server.clone());
+        hal9_core::mcp::serve_stdio(&mcp_state.mcp).await?;
+        server.shutdown().await?;
+        return Ok(());
+    }
+    
     // Create HTTP API router
     let api_router = api::create_api_router(server.clone());
     
@@ -64,10 +95,31 @@ async fn main() -> Result<()> {
         }
     });
     
-    // Wait for shutdown signal
-    shutdown_signal().await;
+    // Reload network TLS certificates on SIGHUP
+    #[cfg(unix)]
+    {
+        let server = server.clone();
+        tokio::spawn(async move {
+            let mut hangup = match signal::unix::signal(signal::unix::SignalKind::hangup()) {
+                Ok(hangup) => hangup,
+                Err(e) => {
+                    error!("Failed to install SIGHUP handler: {}", e);
+                    return;
+                }
+            };
+            while hangup.recv().await.is_some() {
+                info!("SIGHUP received, reloading network TLS certificates");
+                if let Err(e) = server.reload_network_tls().await {
+                    error!("TLS reload failed: {}", e);
+                }
+            }
+        });
+    }
+    
+    // Wait for shutdown; SIGTERM drains running chains first
+    shutdown_signal(&server).await;
     
-    info!("Shutdown signal received, stopping server...");
+    info!("Stopping server...");
     
     // Shutdown server
     server.shutdown().await?;
@@ -79,129 +131,68 @@ async fn main() -> Result<()> {
     Ok(())
 }
 
-async fn load_config() -> Result<ServerConfig> {
+/// Apply pending migrations to the configured database, whatever
+/// `database.migrate_on_start` says, and exit
+async fn migrate_only(config: &ServerConfig) -> Result<()> {
+    let pool = database_migrations::connect(&config.database)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("{} needs database.url in the configuration", MIGRATE_ONLY_FLAG))?;
+    let timeout = std::time::Duration::from_secs(config.database.lock_timeout_secs);
+    let result = database_migrations::migrate(&pool, timeout).await;
+    pool.close().await;
+    let report = result?;
+    
+    match report.to {
+        Some(version) if report.applied.is_empty() => info!("Database schema is current at version {}", version),
+        Some(version) => info!("Applied {} migration(s); database schema is at version {}", report.applied.len(), version),
+        None => info!("No migrations to apply"),
+    }
+    Ok(())
+}
+
+async fn load_config() -> Result<LayeredConfig> {
     // Check for config file argument
-    let args: Vec<String> = std::env::args().collect();
+    let args: Vec<String> = std::env::args()
+        .filter(|arg| arg != MCP_STDIO_FLAG && arg != MIGRATE_ONLY_FLAG)
+        .collect();
     
     if args.len() > 1 {
         // Load from specified file
         let config_path = &args[1];
         info!("Loading configuration from: {}", config_path);
         let config_str = tokio::fs::read_to_string(config_path).await?;
-        let config: ServerConfig = serde_yaml::from_str(&config_str)?;
-        Ok(config)
+        let file: serde_json::Value = serde_yaml::from_str(&config_str)?;
+        let mut layers = LayeredConfig::new(Some(file), std::env::vars())?;
+        layers.decrypt_secrets(&EnvKeyProvider)?;
+        Ok(layers)
     } else {
         // Create default config for testing
         info!("Using default configuration");
-        Ok(create_default_config())
+        Ok(LayeredConfig::with_env(dev::default_config(), std::env::vars())?)
     }
 }
 
-fn create_default_config() -> ServerConfig {
-    use hal9_core::{NeuronConfig, config::{ClaudeConfig, MonitoringConfig, MockResponse, NetworkConfig}};
-    use std::collections::HashMap;
-    
-    // Create mock responses for demo
-    let mut mock_responses = HashMap::new();
-    
-    // L4 responses
-    mock_responses.insert("L4".to_string(), vec![
-        MockResponse {
-            trigger: "default".to_string(),
-            response: "FORWARD_TO: neuron-l3-design\nCONTENT: Breaking down the request into design requirements".to_string(),
-            delay_ms: 100,
-        },
-    ]);
-    
-    // L3 responses
-    mock_responses.insert("L3".to_string(), vec![
-        MockResponse {
-            trigger: "default".to_string(),
-            response: "FORWARD_TO: neuron-l2-impl\nCONTENT: Creating implementation plan based on design".to_string(),
-            delay_ms: 100,
-        },
-    ]);
-    
-    // L2 responses
-    mock_responses.insert("L2".to_string(), vec![
-        MockResponse {
-            trigger: "default".to_string(),
-            response: "RESULT: Implementation complete\n```python\n# Generated code\nprint('Hello from 2HAL9!')\n```".to_string(),
-            delay_ms: 100,
-        },
-    ]);
-    
-    ServerConfig {
-        server_id: "hal9-server-1".to_string(),
-        neurons: vec![
-            NeuronConfig {
-                id: "neuron-l4-strategic".to_string(),
-                layer: "L4".to_string(),
-                claude_command: "claude".to_string(),
-                system_prompt: Some("You are a strategic layer neuron".to_string()),
-                forward_connections: vec!["neuron-l3-design".to_string()],
-                backward_connections: vec![],
-                settings: HashMap::new(),
-            },
-            NeuronConfig {
-                id: "neuron-l3-design".to_string(),
-                layer: "L3".to_string(),
-                claude_command: "claude".to_string(),
-                system_prompt: Some("You are a design layer neuron".to_string()),
-                forward_connections: vec!["neuron-l2-impl".to_string()],
-                backward_connections: vec!["neuron-l4-strategic".to_string()],
-                settings: HashMap::new(),
-            },
-            NeuronConfig {
-                id: "neuron-l2-impl".to_string(),
-                layer: "L2".to_string(),
-                claude_command: "claude".to_string(),
-                system_prompt: Some("You are an implementation layer neuron".to_string()),
-                forward_connections: vec![],
-                backward_connections: vec!["neuron-l3-design".to_string()],
-                settings: HashMap::new(),
-            },
-        ],
-        claude: ClaudeConfig {
-            mode: "mock".to_string(),
-            api_key: None,
-            model: "claude-3-sonnet-20240229".to_string(),
-            temperature: 0.7,
-            max_tokens: 4000,
-            rate_limit: 10,
-            mock_responses,
-            fallback_to_mock: false, // Not needed in mock mode
-            cost_controls: Default::default(),
-        },
-        monitoring: MonitoringConfig::default(),
-        network: NetworkConfig::default(),
-        memory: Default::default(),
-        backward_propagation: Default::default(),
-        auth: Default::default(),
-        browser: None,
-    }
-}
-
-async fn shutdown_signal() {
-    let ctrl_c = async {
-        signal::ctrl_c()
-            .await
-            .expect("failed to install Ctrl+C handler");
-    };
-
+/// Wait for Ctrl+C, which stops at once, or for SIGTERM, which stops once
+/// the server has drained or the drain timeout has passed
+async fn shutdown_signal(server: &Arc<HAL9Server>) {
+    let autoscaler = server.autoscaler();
+    
     #[cfg(unix)]
-    let terminate = async {
-        signal::unix::signal(signal::unix::SignalKind::terminate())
-            .expect("failed to install signal handler")
-            .recv()
-            .await;
-    };
-
+    let drained = autoscaler.drain_on_sigterm()
+        .expect("failed to install signal handler");
+    
     #[cfg(not(unix))]
-    let terminate = std::future::pending::<()>();
+    let drained = autoscaler.drain_on(std::future::pending::<()>());
 
     tokio::select! {
-        _ = ctrl_c => {},
-        _ = terminate => {},
+        result = signal::ctrl_c() => {
+            result.expect("failed to install Ctrl+C handler");
+            info!("Ctrl+C received");
+        }
+        outcome = drained => match outcome {
+            Ok(DrainOutcome::Drained) => info!("Drain complete"),
+            Ok(DrainOutcome::TimedOut) => warn!("Drain timed out, stopping with chains still running"),
+            Err(e) => error!("Drain task failed: {}", e),
+        },
     }
-}
\ No newline at end of file
+}
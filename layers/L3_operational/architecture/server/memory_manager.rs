@@ -1,22 +1,51 @@
 //! Memory management for neurons
+//!
+//! Memories tuned in one environment are promoted to another with a dump:
+//! JSON lines, a [`DumpHeader`] followed by one [`DumpEntry`] per memory.
+//! An imported entry conflicts with a stored one of the same key, and the
+//! [`ConflictPolicy`] decides which is kept. Embeddings are carried over as
+//! they are, or regenerated when the dump came from a different embedding
+//! provider and the import asks for it. A [`DumpScope`] confines a dump to
+//! one organization's entries.
 
 use std::sync::Arc;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
 use tracing::{info, error};
+use uuid::Uuid;
 
 use hal9_core::{
     Result, Error,
-    memory::{MemoryStore, SqliteMemoryStore},
+    encryption::TenantKeyring,
+    metadata_schema::keys,
+    memory::{
+        ColdTier, EmbeddingGenerator, MemoryEntry, MemoryReview, MemoryStore, MemoryType, SealedSearch,
+        SqliteMemoryStore,
+    },
+    sqlite::SqlitePools,
     config::{MemoryConfig, MemoryCleanupConfig},
 };
 
+use crate::memory_tiering;
+
+/// Format name in a dump's header line
+pub const DUMP_FORMAT: &str = "hal9-memory";
+
+/// Version of the dump format written by [`export_dump`]
+pub const DUMP_VERSION: u32 = 1;
+
 /// Memory manager for initializing and managing neuron memory
 pub struct MemoryManager {
     store: Arc<dyn MemoryStore>,
+    sqlite: Arc<SqliteMemoryStore>,
+    pools: SqlitePools,
 }
 
 impl MemoryManager {
-    /// Create a new memory manager
-    pub async fn new(config: &MemoryConfig) -> Result<Self> {
+    /// Create a new memory manager; with a keyring, memories of
+    /// organizations with encryption enabled are sealed at rest. Cold
+    /// entries are read from the tier `memory.tiering.backend` names.
+    pub async fn new(config: &MemoryConfig, sealing: Option<(Arc<TenantKeyring>, SealedSearch)>) -> Result<Self> {
         if !config.enabled {
             return Err(Error::Config("Memory system is disabled".to_string()));
         }
@@ -24,15 +53,23 @@ impl MemoryManager {
         info!("Initializing memory system at: {}", config.database_path);
         
         // Create SQLite memory store
-        let store = SqliteMemoryStore::new(&config.database_path).await?;
+        let mut store = SqliteMemoryStore::new(&config.database_path).await?;
+        if let Some((keyring, sealed_search)) = sealing {
+            store = store.with_keyring(keyring, sealed_search);
+        }
+        let cold = memory_tiering::object_store(&config.tiering.backend)?;
+        store = store.with_cold_tier(Arc::new(ColdTier::new(cold, config.tiering.promote_after_reads)));
         
         // Initialize the database schema
         store.initialize().await?;
         
         info!("Memory system initialized successfully");
         
+        let sqlite = Arc::new(store);
         Ok(Self {
-            store: Arc::new(store),
+            pools: sqlite.pools().clone(),
+            store: sqlite.clone(),
+            sqlite,
         })
     }
     
@@ -41,6 +78,16 @@ impl MemoryManager {
         self.store.clone()
     }
     
+    /// The store itself, for moving entries between tiers
+    pub fn sqlite_store(&self) -> Arc<SqliteMemoryStore> {
+        self.sqlite.clone()
+    }
+    
+    /// Reader and writer pools of the memory database
+    pub fn pools(&self) -> SqlitePools {
+        self.pools.clone()
+    }
+    
     /// Run cleanup based on configuration
     pub async fn cleanup(&self, config: &MemoryCleanupConfig) -> Result<u64> {
         let before = chrono::Utc::now() - chrono::Duration::days(config.retention_days as i64);
@@ -72,4 +119,330 @@ pub async fn cleanup_task(manager: Arc<MemoryManager>, config: MemoryCleanupConf
             }
         }
     }
-}
\ No newline at end of file
+}
+/// First line of a memory dump
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DumpHeader {
+    pub format: String,
+    pub version: u32,
+    pub exported_at: DateTime<Utc>,
+    /// Provider of the dump's embeddings, see [`EmbeddingGenerator::provider`]
+    pub embedding_provider: String,
+    /// Filters the dump was exported with
+    pub namespace: Option<String>,
+    pub neuron_id: Option<String>,
+    pub entries: usize,
+}
+
+/// One memory in a dump
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DumpEntry {
+    pub namespace: String,
+    /// Entry ID; an entry with the same key in the target conflicts
+    pub key: Uuid,
+    /// Entry content
+    pub value: String,
+    pub neuron_id: String,
+    pub layer: String,
+    pub entry_type: MemoryType,
+    pub metadata: serde_json::Value,
+    pub importance: f32,
+    pub access_count: u32,
+    /// Times outputs cited the entry
+    #[serde(default)]
+    pub citations: u32,
+    pub has_embedding: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub embedding: Option<Vec<f32>>,
+    pub created_at: DateTime<Utc>,
+    pub last_accessed: DateTime<Utc>,
+    pub expires_at: Option<DateTime<Utc>>,
+    /// Summary the entry was archived into
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub archived_into: Option<Uuid>,
+    /// Entries a summary was written from
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub summary_of: Vec<Uuid>,
+}
+
+impl From<MemoryEntry> for DumpEntry {
+    fn from(entry: MemoryEntry) -> Self {
+        Self {
+            namespace: entry.namespace,
+            key: entry.id,
+            value: entry.content,
+            neuron_id: entry.neuron_id,
+            layer: entry.layer,
+            entry_type: entry.entry_type,
+            metadata: entry.metadata,
+            importance: entry.importance,
+            access_count: entry.access_count,
+            citations: entry.citations,
+            has_embedding: entry.embedding.is_some(),
+            embedding: entry.embedding,
+            created_at: entry.timestamp,
+            last_accessed: entry.last_accessed,
+            expires_at: entry.expires_at,
+            archived_into: entry.archived_into,
+            summary_of: entry.summary_of,
+        }
+    }
+}
+
+impl From<DumpEntry> for MemoryEntry {
+    fn from(entry: DumpEntry) -> Self {
+        Self {
+            id: entry.key,
+            neuron_id: entry.neuron_id,
+            layer: entry.layer,
+            timestamp: entry.created_at,
+            entry_type: entry.entry_type,
+            content: entry.value,
+            metadata: entry.metadata,
+            embedding: entry.embedding,
+            importance: entry.importance,
+            access_count: entry.access_count,
+            last_accessed: entry.last_accessed,
+            namespace: entry.namespace,
+            expires_at: entry.expires_at,
+            review: MemoryReview::Approved,
+            review_reason: None,
+            citations: entry.citations,
+            archived_into: entry.archived_into,
+            summary_of: entry.summary_of,
+        }
+    }
+}
+
+impl DumpEntry {
+    /// Same memory, ignoring how often and when it was last read
+    fn same_content(&self, other: &DumpEntry) -> bool {
+        self.namespace == other.namespace
+            && self.value == other.value
+            && self.neuron_id == other.neuron_id
+            && self.layer == other.layer
+            && self.entry_type == other.entry_type
+            && self.metadata == other.metadata
+            && self.importance == other.importance
+            && self.embedding == other.embedding
+            && self.expires_at == other.expires_at
+    }
+}
+
+/// Which entry is kept when an imported key already exists
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ConflictPolicy {
+    /// Keep the stored entry
+    #[default]
+    Skip,
+    /// Replace it with the imported one
+    Overwrite,
+    /// Replace it if the imported entry was created later
+    MergeNewer,
+}
+
+/// How to import a dump
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ImportOptions {
+    #[serde(default)]
+    pub policy: ConflictPolicy,
+    /// Report what would change without writing anything
+    #[serde(default)]
+    pub dry_run: bool,
+    /// Regenerate embeddings when the dump's provider differs from ours
+    #[serde(default)]
+    pub recompute_embeddings: bool,
+}
+
+/// What an import does with one entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportAction {
+    Create,
+    Overwrite,
+    /// Kept the stored entry under the conflict policy
+    Skip,
+    /// The stored entry already holds the same memory
+    Unchanged,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ImportChange {
+    pub key: Uuid,
+    pub namespace: String,
+    pub action: ImportAction,
+}
+
+/// Outcome of an import, or with `dry_run` what it would be
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ImportReport {
+    pub dry_run: bool,
+    pub policy: ConflictPolicy,
+    pub source_provider: String,
+    pub created: usize,
+    pub overwritten: usize,
+    pub skipped: usize,
+    pub unchanged: usize,
+    pub embeddings_recomputed: usize,
+    pub changes: Vec<ImportChange>,
+}
+
+/// Entries a dump may carry
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DumpScope {
+    /// Every entry, whatever its organization
+    All,
+    /// Entries attributed to the organization, or with `None` those
+    /// attributed to none
+    Org(Option<String>),
+}
+
+impl DumpScope {
+    /// Whether an entry with `metadata` is in scope
+    pub fn allows(&self, metadata: &serde_json::Value) -> bool {
+        match self {
+            DumpScope::All => true,
+            DumpScope::Org(org_id) => {
+                metadata.get(keys::auth::ORG_ID).and_then(serde_json::Value::as_str) == org_id.as_deref()
+            }
+        }
+    }
+}
+
+/// Header and entry lines of a dump of `store`, optionally of one
+/// namespace and neuron. Expired entries and those out of `scope` are left
+/// out.
+pub async fn export_dump(
+    store: &dyn MemoryStore,
+    namespace: Option<&str>,
+    neuron_id: Option<&str>,
+    scope: &DumpScope,
+    embeddings: &EmbeddingGenerator,
+) -> Result<Vec<String>> {
+    let mut entries = store.export(namespace, neuron_id).await?;
+    entries.retain(|entry| scope.allows(&entry.metadata));
+    let header = DumpHeader {
+        format: DUMP_FORMAT.to_string(),
+        version: DUMP_VERSION,
+        exported_at: Utc::now(),
+        embedding_provider: embeddings.provider(),
+        namespace: namespace.map(str::to_string),
+        neuron_id: neuron_id.map(str::to_string),
+        entries: entries.len(),
+    };
+    
+    let mut lines = Vec::with_capacity(entries.len() + 1);
+    lines.push(serde_json::to_string(&header).map_err(|e| Error::Serialization(e.to_string()))?);
+    for entry in entries {
+        let line = serde_json::to_string(&DumpEntry::from(entry))
+            .map_err(|e| Error::Serialization(e.to_string()))?;
+        lines.push(line);
+    }
+    Ok(lines)
+}
+
+/// Parse a dump, failing on the first malformed line
+pub fn parse_dump(dump: &str) -> Result<(DumpHeader, Vec<DumpEntry>)> {
+    let mut lines = dump.lines().enumerate().filter(|(_, line)| !line.trim().is_empty());
+    
+    let (_, first) = lines.next()
+        .ok_or_else(|| Error::InvalidInput("Memory dump is empty".to_string()))?;
+    let header: DumpHeader = serde_json::from_str(first)
+        .map_err(|e| Error::InvalidInput(format!("Line 1 is not a memory dump header: {}", e)))?;
+    if header.format != DUMP_FORMAT {
+        return Err(Error::InvalidInput(format!("Not a memory dump: format is '{}'", header.format)));
+    }
+    if header.version > DUMP_VERSION {
+        return Err(Error::InvalidInput(format!(
+            "Memory dump version {} is newer than the supported version {}",
+            header.version, DUMP_VERSION
+        )));
+    }
+    
+    let entries = lines
+        .map(|(index, line)| {
+            serde_json::from_str(line)
+                .map_err(|e| Error::InvalidInput(format!("Line {} is not a memory entry: {}", index + 1, e)))
+        })
+        .collect::<Result<Vec<DumpEntry>>>()?;
+    Ok((header, entries))
+}
+
+/// Import a dump into `store` under `options`. Every line is parsed and
+/// checked against `scope` before anything is written, so a malformed dump,
+/// or one with an entry out of scope or keyed like a stored entry out of
+/// scope, changes nothing.
+pub async fn import_dump(
+    store: &dyn MemoryStore,
+    dump: &str,
+    options: &ImportOptions,
+    scope: &DumpScope,
+    embeddings: &EmbeddingGenerator,
+) -> Result<ImportReport> {
+    let (header, entries) = parse_dump(dump)?;
+    for entry in &entries {
+        let stored_in_scope = match store.get(entry.key).await? {
+            Some(stored) => scope.allows(&stored.metadata),
+            None => true,
+        };
+        if !scope.allows(&entry.metadata) || !stored_in_scope {
+            return Err(Error::PermissionDenied(format!(
+                "Memory entry {} belongs to another organization",
+                entry.key
+            )));
+        }
+    }
+    let recompute = options.recompute_embeddings && header.embedding_provider != embeddings.provider();
+    
+    let mut report = ImportReport {
+        dry_run: options.dry_run,
+        policy: options.policy,
+        source_provider: header.embedding_provider,
+        created: 0,
+        overwritten: 0,
+        skipped: 0,
+        unchanged: 0,
+        embeddings_recomputed: 0,
+        changes: Vec::with_capacity(entries.len()),
+    };
+    
+    for mut entry in entries {
+        if recompute && entry.has_embedding {
+            entry.embedding = Some(embeddings.generate(&entry.value).await?);
+            report.embeddings_recomputed += 1;
+        }
+        
+        let existing = store.get(entry.key).await?.map(DumpEntry::from);
+        let action = match existing {
+            None => ImportAction::Create,
+            Some(existing) if existing.same_content(&entry) => ImportAction::Unchanged,
+            Some(existing) => match options.policy {
+                ConflictPolicy::Skip => ImportAction::Skip,
+                ConflictPolicy::Overwrite => ImportAction::Overwrite,
+                ConflictPolicy::MergeNewer if entry.created_at > existing.created_at => ImportAction::Overwrite,
+                ConflictPolicy::MergeNewer => ImportAction::Skip,
+            },
+        };
+        
+        match action {
+            ImportAction::Create => report.created += 1,
+            ImportAction::Overwrite => report.overwritten += 1,
+            ImportAction::Skip => report.skipped += 1,
+            ImportAction::Unchanged => report.unchanged += 1,
+        }
+        report.changes.push(ImportChange { key: entry.key, namespace: entry.namespace.clone(), action });
+        
+        if !options.dry_run && matches!(action, ImportAction::Create | ImportAction::Overwrite) {
+            store.replace(entry.into()).await?;
+        }
+    }
+    
+    if !options.dry_run {
+        info!(
+            "Imported memory dump: {} created, {} overwritten, {} skipped, {} unchanged",
+            report.created, report.overwritten, report.skipped, report.unchanged
+        );
+    }
+    Ok(report)
+}
@@ -0,0 +1,72 @@
+//! Automatic review of pending memory writes
+//!
+//! A strategic neuron that writes a confidently wrong lesson into memory
+//! poisons every later prompt that recalls it. Writes to moderated
+//! namespaces therefore wait for approval, and [`JudgeReviewer`] gives that
+//! approval automatically: a model compares the write with the approved
+//! entries of its namespace and rejects it when the two contradict.
+
+use async_trait::async_trait;
+
+use hal9_core::memory::{MemoryEntry, MemoryReviewer, ReviewVerdict};
+use hal9_core::Result;
+
+use crate::claude::ClaudeInterface;
+
+/// Approves pending writes a model finds consistent with approved memory
+pub struct JudgeReviewer {
+    claude: Box<dyn ClaudeInterface>,
+}
+
+impl JudgeReviewer {
+    pub fn new(claude: Box<dyn ClaudeInterface>) -> Self {
+        Self { claude }
+    }
+}
+
+/// Prompt asking whether `entry` is consistent with `approved`
+pub fn judge_prompt(entry: &MemoryEntry, approved: &[MemoryEntry]) -> String {
+    let mut prompt = String::from(
+        "You review a lesson a neuron wants to add to shared memory. Reject it if it contradicts the \
+         approved memory below or states something as certain that the approved memory does not support. \
+         Entries neuron outputs have cited proved useful; weigh a contradiction with one of them more.\n\n\
+         APPROVED MEMORY:\n",
+    );
+    if approved.is_empty() {
+        prompt.push_str("(none)\n");
+    }
+    for existing in approved {
+        match existing.citations {
+            0 => prompt.push_str(&format!("- {}\n", existing.content)),
+            cited => prompt.push_str(&format!("- {} (cited {} times)\n", existing.content, cited)),
+        }
+    }
+    prompt.push_str(&format!(
+        "\nNEW ENTRY (from {}, {}):\n{}\n\nAnswer with APPROVE, or REJECT: <reason>.",
+        entry.neuron_id, entry.layer, entry.content
+    ));
+    prompt
+}
+
+/// Verdict in a judge's answer; anything else leaves the write pending
+pub fn parse_verdict(answer: &str) -> ReviewVerdict {
+    let answer = answer.trim();
+    let upper = answer.to_uppercase();
+    if upper.starts_with("APPROVE") {
+        ReviewVerdict::Approve
+    } else if upper.starts_with("REJECT") {
+        let reason = answer["REJECT".len()..].trim_start_matches([':', ' ']).trim();
+        let reason = if reason.is_empty() { "rejected by the memory judge" } else { reason };
+        ReviewVerdict::Reject(reason.to_string())
+    } else {
+        ReviewVerdict::Defer
+    }
+}
+
+#[async_trait]
+impl MemoryReviewer for JudgeReviewer {
+    async fn review(&self, entry: &MemoryEntry, approved: &[MemoryEntry]) -> Result<ReviewVerdict> {
+        let answer = self.claude.send_message(&judge_prompt(entry, approved)).await?;
+        Ok(parse_verdict(&answer))
+    }
+}
@@ -0,0 +1,335 @@
+//! Summarization of memory namespaces near their quota
+//!
+//! The summarization job looks for namespaces holding `high_water` of their
+//! entry or byte quota, as configured under `memory.summarization`. It
+//! clusters the oldest entries of each by embedding, has a cheap model write
+//! a summary of every cluster large enough, largest first, and archives the
+//! entries of the cluster into the summary and to the cold tier, until the
+//! namespace is under `low_water` of its quota. See
+//! [`hal9_core::memory::summarization`] for how archived entries are read.
+//!
+//! Summaries are written at most `summaries_per_minute` and, once a UTC
+//! day's summaries cost `max_cost_per_day`, not at all until the next day.
+//! Every merge is written to the audit log with the summary and the entries
+//! archived into it. Runs are skipped while the server is read-only.
+
+use std::cmp::Reverse;
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDate, Utc};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, error, info, warn};
+use uuid::Uuid;
+
+use hal9_core::{
+    config::MemorySummarizationConfig,
+    memory::{
+        summarization::{cluster, summary_entry},
+        EmbeddingGenerator, MemoryEntry, MemoryNamespace, MemorySummarizer, NamespacedMemory, SqliteMemoryStore,
+        Summary,
+    },
+    Error,
+};
+
+use crate::{
+    claude::{model_pricing, ClaudeInterface},
+    error::{ServerError, ServerResult},
+    read_only::ReadOnlyGate,
+};
+
+/// Summarizes clusters of entries with a model
+pub struct ModelSummarizer {
+    claude: Box<dyn ClaudeInterface>,
+}
+
+impl ModelSummarizer {
+    pub fn new(claude: Box<dyn ClaudeInterface>) -> Self {
+        Self { claude }
+    }
+}
+
+/// Prompt asking for one entry saying what `entries` say
+pub fn summary_prompt(entries: &[MemoryEntry]) -> String {
+    let mut prompt = String::from(
+        "You consolidate the memory of a team of neurons. Merge the entries below into one entry that \
+         keeps every distinct fact, lesson and error pattern they state, drops repetition and says where \
+         they disagree. Answer with the merged entry only.\n\nENTRIES (oldest first):\n",
+    );
+    for entry in entries {
+        prompt.push_str(&format!("- {}\n", entry.content));
+    }
+    prompt
+}
+
+#[async_trait]
+impl MemorySummarizer for ModelSummarizer {
+    async fn summarize(&self, entries: &[MemoryEntry]) -> hal9_core::Result<Summary> {
+        let content = self.claude.send_message(&summary_prompt(entries)).await?;
+        let cost_usd = self.claude.last_token_usage().map_or(0.0, |usage| {
+            let (per_1k_prompt, per_1k_completion) = model_pricing(usage.model.as_deref().unwrap_or_default());
+            usage.prompt_tokens as f64 / 1000.0 * per_1k_prompt
+                + usage.completion_tokens as f64 / 1000.0 * per_1k_completion
+        });
+        let content = content.trim();
+        if content.is_empty() {
+            return Err(Error::InvalidInput("The summarizer answered with an empty summary".to_string()));
+        }
+        Ok(Summary { content: content.to_string(), cost_usd })
+    }
+}
+
+/// A summary written and the entries archived into it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SummaryMerge {
+    pub namespace: String,
+    pub summary_id: Uuid,
+    pub archived: Vec<Uuid>,
+    pub cost_usd: f64,
+}
+
+/// What one summarization run did
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SummarizationReport {
+    pub ran_at: DateTime<Utc>,
+    /// Namespaces found over the high-water mark
+    pub namespaces: usize,
+    pub merges: Vec<SummaryMerge>,
+    /// Clusters whose summary failed; their entries stay as they are
+    pub failed: usize,
+    /// Clusters with an entry that changed or went away while summarized
+    pub skipped: usize,
+    /// Spent on summaries, the skipped included
+    pub cost_usd: f64,
+    /// Whether the run stopped at the daily cost cap
+    pub capped: bool,
+}
+
+/// Summarization settings, today's spending and the last run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SummarizationStatus {
+    pub enabled: bool,
+    pub spent_today_usd: f64,
+    pub max_cost_per_day: f64,
+    pub last_run: Option<SummarizationReport>,
+}
+
+/// Summarizes namespaces near their quota
+pub struct SummarizationJob {
+    config: MemorySummarizationConfig,
+    memory: Arc<NamespacedMemory>,
+    store: Arc<SqliteMemoryStore>,
+    summarizer: Arc<dyn MemorySummarizer>,
+    embeddings: EmbeddingGenerator,
+    embedding_dimension: usize,
+    /// UTC day and what summaries cost on it
+    spent: Mutex<(NaiveDate, f64)>,
+    last_run: Mutex<Option<SummarizationReport>>,
+    /// One run at a time
+    running: tokio::sync::Mutex<()>,
+}
+
+impl SummarizationJob {
+    /// A job summarizing the namespaces of `memory`, which `store` backs,
+    /// clustering entries by embeddings of `embedding_dimension`
+    pub fn new(
+        config: MemorySummarizationConfig,
+        memory: Arc<NamespacedMemory>,
+        store: Arc<SqliteMemoryStore>,
+        summarizer: Arc<dyn MemorySummarizer>,
+        embedding_dimension: usize,
+    ) -> Self {
+        Self {
+            config,
+            memory,
+            store,
+            summarizer,
+            embeddings: EmbeddingGenerator::new(embedding_dimension),
+            embedding_dimension,
+            spent: Mutex::new((NaiveDate::MIN, 0.0)),
+            last_run: Mutex::new(None),
+            running: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    /// Summarize every namespace over the high-water mark at `now`
+    pub async fn run(&self, now: DateTime<Utc>) -> ServerResult<SummarizationReport> {
+        let _running = self.running.lock().await;
+        let mut report = SummarizationReport {
+            ran_at: now,
+            ..Default::default()
+        };
+
+        for stats in self.memory.stats().await.map_err(internal)? {
+            if fill(stats.entries, stats.bytes, stats.max_entries, stats.max_bytes) < self.config.high_water {
+                continue;
+            }
+            report.namespaces += 1;
+            if !self.summarize_namespace(&stats.namespace, now, &mut report).await? {
+                break;
+            }
+        }
+
+        *self.last_run.lock() = Some(report.clone());
+        Ok(report)
+    }
+
+    /// Summarize clusters of `namespace` until it is under the low-water
+    /// mark. Returns false once the daily cost cap is reached.
+    async fn summarize_namespace(
+        &self,
+        namespace: &str,
+        now: DateTime<Utc>,
+        report: &mut SummarizationReport,
+    ) -> ServerResult<bool> {
+        let candidates = self.store
+            .summarization_candidates(namespace, self.config.batch_size)
+            .await
+            .map_err(internal)?;
+        let mut embeddings = Vec::with_capacity(candidates.len());
+        for entry in &candidates {
+            embeddings.push(self.embedding_of(entry).await?);
+        }
+        let mut clusters: Vec<Vec<usize>> =
+            cluster(&candidates, &embeddings, self.config.min_similarity, self.config.max_cluster_size)
+                .into_iter()
+                .filter(|members| members.len() >= self.config.min_cluster_size.max(2))
+                .collect();
+        clusters.sort_by_key(|members| Reverse(members.len()));
+
+        let pause = (self.config.summaries_per_minute > 0)
+            .then(|| Duration::from_secs_f64(60.0 / self.config.summaries_per_minute as f64));
+        for members in clusters {
+            if self.fill(namespace).await? < self.config.low_water {
+                break;
+            }
+            if self.config.max_cost_per_day > 0.0 && self.spent_on(now.date_naive()) >= self.config.max_cost_per_day {
+                warn!("Memory summarization stopped for today at its cost cap of ${:.2}", self.config.max_cost_per_day);
+                report.capped = true;
+                return Ok(false);
+            }
+            if let (Some(pause), true) = (pause, report.merges.len() + report.failed + report.skipped > 0) {
+                tokio::time::sleep(pause).await;
+            }
+
+            let entries: Vec<MemoryEntry> = members.iter().map(|&i| candidates[i].clone()).collect();
+            let summary = match self.summarizer.summarize(&entries).await {
+                Ok(summary) => summary,
+                Err(e) => {
+                    warn!("Failed to summarize {} memory entries of {}: {}", entries.len(), namespace, e);
+                    report.failed += 1;
+                    continue;
+                }
+            };
+            self.record_spend(now.date_naive(), summary.cost_usd);
+            report.cost_usd += summary.cost_usd;
+
+            let mut entry = summary_entry(&entries, summary.content, now);
+            entry.embedding = Some(self.embeddings.generate(&entry.content).await.map_err(internal)?);
+            if !self.store.store_summary(&entry).await.map_err(internal)? {
+                debug!("Entries of a {} cluster changed while summarized, skipping it", namespace);
+                report.skipped += 1;
+                continue;
+            }
+            for original in &entry.summary_of {
+                if let Err(e) = self.store.demote(*original).await {
+                    warn!("Archived memory {} stays in the hot tier: {}", original, e);
+                }
+            }
+
+            info!(
+                target: "audit",
+                event = "memory_summarized",
+                namespace,
+                summary_id = %entry.id,
+                archived = %entry.summary_of.iter().map(Uuid::to_string).collect::<Vec<_>>().join(","),
+                cost_usd = summary.cost_usd,
+                "Merged {} memory entries of {} into summary {}", entry.summary_of.len(), namespace, entry.id
+            );
+            report.merges.push(SummaryMerge {
+                namespace: namespace.to_string(),
+                summary_id: entry.id,
+                archived: entry.summary_of,
+                cost_usd: summary.cost_usd,
+            });
+        }
+        Ok(true)
+    }
+
+    /// Stored embedding of `entry`, or one generated from its content when
+    /// it has none or one of another dimension
+    async fn embedding_of(&self, entry: &MemoryEntry) -> ServerResult<Vec<f32>> {
+        match &entry.embedding {
+            Some(embedding) if embedding.len() == self.embedding_dimension => Ok(embedding.clone()),
+            _ => self.embeddings.generate(&entry.content).await.map_err(internal),
+        }
+    }
+
+    /// Share of its quota `namespace` fills
+    async fn fill(&self, namespace: &str) -> ServerResult<f32> {
+        let limits = namespace.parse::<MemoryNamespace>()
+            .map(|ns| self.memory.limits_for(&ns).clone())
+            .map_err(internal)?;
+        let usage = self.memory.store().namespace_usage(Some(namespace)).await.map_err(internal)?;
+        Ok(usage.first().map_or(0.0, |usage| fill(usage.entries, usage.bytes, limits.max_entries, limits.max_bytes)))
+    }
+
+    fn spent_on(&self, day: NaiveDate) -> f64 {
+        match *self.spent.lock() {
+            (spent_day, spent) if spent_day == day => spent,
+            _ => 0.0,
+        }
+    }
+
+    fn record_spend(&self, day: NaiveDate, cost_usd: f64) {
+        let mut spent = self.spent.lock();
+        if spent.0 != day {
+            *spent = (day, 0.0);
+        }
+        spent.1 += cost_usd;
+    }
+
+    pub fn status(&self) -> SummarizationStatus {
+        SummarizationStatus {
+            enabled: self.config.enabled,
+            spent_today_usd: self.spent_on(Utc::now().date_naive()),
+            max_cost_per_day: self.config.max_cost_per_day,
+            last_run: self.last_run.lock().clone(),
+        }
+    }
+}
+
+/// Share of the tighter of its quotas a namespace fills; 0 without quotas
+fn fill(entries: u64, bytes: u64, max_entries: Option<u64>, max_bytes: Option<u64>) -> f32 {
+    let share = |used: u64, max: Option<u64>| max.map_or(0.0, |max| used as f32 / max.max(1) as f32);
+    share(entries, max_entries).max(share(bytes, max_bytes))
+}
+
+fn internal(e: Error) -> ServerError {
+    ServerError::Internal(e.to_string())
+}
+
+/// Run the summarization job every `memory.summarization.interval_secs`,
+/// except while the server is read-only
+pub async fn summarization_task(job: Arc<SummarizationJob>, read_only: Arc<ReadOnlyGate>) {
+    let mut interval = tokio::time::interval(Duration::from_secs(job.config.interval_secs.max(1)));
+    loop {
+        interval.tick().await;
+        if read_only.is_active() {
+            debug!("Skipping memory summarization while read-only");
+            continue;
+        }
+        match job.run(Utc::now()).await {
+            Ok(report) if report.merges.is_empty() => debug!("Memory summarization merged nothing"),
+            Ok(report) => info!(
+                "Memory summarization merged {} entries into {} summaries for ${:.4}",
+                report.merges.iter().map(|merge| merge.archived.len()).sum::<usize>(),
+                report.merges.len(),
+                report.cost_usd
+            ),
+            Err(e) => error!("Memory summarization failed: {}", e),
+        }
+    }
+}
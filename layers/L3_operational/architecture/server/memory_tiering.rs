@@ -0,0 +1,377 @@
+//! Cold tiering of memory content
+//!
+//! The tiering job moves the content of idle and unimportant memory entries
+//! to the cold tier configured under `memory.tiering`, a local directory or
+//! an S3-compatible bucket, leaving stub rows in the memory database; see
+//! [`hal9_core::memory::tiering`] for how reads, promotion and crash safety
+//! work. Each run first deletes cold objects no entry refers to, then moves
+//! up to `batch_size` entries, one at a time and at most `moves_per_second`
+//! of them, so reads and writes never queue behind a run for long. Runs are
+//! skipped while the server is read-only.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tracing::{debug, error, info, warn};
+
+use hal9_core::{
+    config::{ColdTierBackend, MemoryTieringConfig},
+    memory::{ColdTierStats, FsObjectStore, ObjectStore, SqliteMemoryStore, TierUsage},
+    secrets::SecretString,
+    Error,
+};
+
+use crate::{
+    error::{ServerError, ServerResult},
+    read_only::ReadOnlyGate,
+    webhooks::to_hex,
+};
+
+/// Object store of the configured backend
+pub fn object_store(backend: &ColdTierBackend) -> hal9_core::Result<Arc<dyn ObjectStore>> {
+    Ok(match backend {
+        ColdTierBackend::Filesystem { dir } => Arc::new(FsObjectStore::new(dir)),
+        ColdTierBackend::S3 { endpoint, bucket, region, prefix, access_key_id, secret_access_key } => Arc::new(
+            S3ObjectStore::new(endpoint, bucket, region, prefix, access_key_id, secret_access_key.clone())?,
+        ),
+    })
+}
+
+/// Objects in an S3-compatible bucket, addressed path-style and signed with
+/// AWS Signature Version 4
+pub struct S3ObjectStore {
+    client: reqwest::Client,
+    endpoint: url::Url,
+    bucket: String,
+    region: String,
+    prefix: String,
+    access_key_id: String,
+    secret_access_key: SecretString,
+}
+
+impl S3ObjectStore {
+    pub fn new(
+        endpoint: &str,
+        bucket: &str,
+        region: &str,
+        prefix: &str,
+        access_key_id: &str,
+        secret_access_key: SecretString,
+    ) -> hal9_core::Result<Self> {
+        let endpoint = url::Url::parse(endpoint)
+            .map_err(|e| Error::Config(format!("Invalid S3 endpoint '{}': {}", endpoint, e)))?;
+        if endpoint.host_str().is_none() {
+            return Err(Error::Config(format!("S3 endpoint '{}' has no host", endpoint)));
+        }
+        Ok(Self {
+            client: reqwest::Client::new(),
+            endpoint,
+            bucket: bucket.to_string(),
+            region: region.to_string(),
+            prefix: prefix.to_string(),
+            access_key_id: access_key_id.to_string(),
+            secret_access_key,
+        })
+    }
+
+    /// Send a signed request for `key`, or for the bucket when `key` is
+    /// `None`
+    async fn send(
+        &self,
+        method: reqwest::Method,
+        key: Option<&str>,
+        query: &[(&str, &str)],
+        body: Vec<u8>,
+    ) -> hal9_core::Result<reqwest::Response> {
+        let mut path = format!("/{}", urlencoding::encode(&self.bucket));
+        if let Some(key) = key {
+            let key = format!("{}{}", self.prefix, key);
+            for segment in key.split('/') {
+                path.push('/');
+                path.push_str(&urlencoding::encode(segment));
+            }
+        }
+        let mut query: Vec<(String, String)> = query.iter()
+            .map(|(name, value)| (urlencoding::encode(name).into_owned(), urlencoding::encode(value).into_owned()))
+            .collect();
+        query.sort();
+        let query = query.iter().map(|(name, value)| format!("{}={}", name, value)).collect::<Vec<_>>().join("&");
+
+        let headers = self.sign(method.as_str(), &path, &query, &body, Utc::now());
+        let mut url = self.endpoint.clone();
+        url.set_path(&path);
+        url.set_query((!query.is_empty()).then_some(query.as_str()));
+        let mut request = self.client.request(method, url).body(body);
+        for (name, value) in headers {
+            request = request.header(name, value);
+        }
+        request.send().await.map_err(|e| Error::Network(format!("S3 request failed: {}", e)))
+    }
+
+    /// Headers signing a request at `now`
+    fn sign(&self, method: &str, path: &str, query: &str, body: &[u8], now: DateTime<Utc>) -> Vec<(&'static str, String)> {
+        let host = match self.endpoint.port() {
+            Some(port) => format!("{}:{}", self.endpoint.host_str().unwrap_or_default(), port),
+            None => self.endpoint.host_str().unwrap_or_default().to_string(),
+        };
+        let payload_hash = to_hex(&Sha256::digest(body));
+        let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let date = now.format("%Y%m%d").to_string();
+        let scope = format!("{}/{}/s3/aws4_request", date, self.region);
+
+        let canonical = format!(
+            "{}\n{}\n{}\nhost:{}\nx-amz-content-sha256:{}\nx-amz-date:{}\n\n{}\n{}",
+            method, path, query, host, payload_hash, amz_date, SIGNED_HEADERS, payload_hash
+        );
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+            amz_date,
+            scope,
+            to_hex(&Sha256::digest(canonical.as_bytes()))
+        );
+        let key = [date.as_str(), self.region.as_str(), "s3", "aws4_request"].iter().fold(
+            format!("AWS4{}", self.secret_access_key.expose()).into_bytes(),
+            |key, part| hmac_sha256(&key, part.as_bytes()),
+        );
+        let signature = to_hex(&hmac_sha256(&key, string_to_sign.as_bytes()));
+
+        vec![
+            ("x-amz-date", amz_date),
+            ("x-amz-content-sha256", payload_hash),
+            (
+                "authorization",
+                format!(
+                    "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+                    self.access_key_id, scope, SIGNED_HEADERS, signature
+                ),
+            ),
+        ]
+    }
+}
+
+const SIGNED_HEADERS: &str = "host;x-amz-content-sha256;x-amz-date";
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// Text of every `<tag>` element of an XML document
+fn xml_values(xml: &str, tag: &str) -> Vec<String> {
+    let (open, close) = (format!("<{}>", tag), format!("</{}>", tag));
+    let mut values = Vec::new();
+    let mut rest = xml;
+    while let Some(start) = rest.find(&open) {
+        rest = &rest[start + open.len()..];
+        let Some(end) = rest.find(&close) else { break };
+        values.push(
+            rest[..end]
+                .replace("&lt;", "<")
+                .replace("&gt;", ">")
+                .replace("&quot;", "\"")
+                .replace("&apos;", "'")
+                .replace("&amp;", "&"),
+        );
+        rest = &rest[end + close.len()..];
+    }
+    values
+}
+
+async fn s3_error(response: reqwest::Response, action: &str) -> Error {
+    let status = response.status();
+    let body = response.text().await.unwrap_or_default();
+    let code = xml_values(&body, "Code").pop().unwrap_or_default();
+    Error::Storage(format!("S3 {} failed ({}): {}", action, status, code))
+}
+
+#[async_trait]
+impl ObjectStore for S3ObjectStore {
+    fn kind(&self) -> &'static str {
+        "s3"
+    }
+
+    async fn put(&self, key: &str, bytes: Vec<u8>) -> hal9_core::Result<()> {
+        let response = self.send(reqwest::Method::PUT, Some(key), &[], bytes).await?;
+        if !response.status().is_success() {
+            return Err(s3_error(response, "put").await);
+        }
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> hal9_core::Result<Option<Vec<u8>>> {
+        let response = self.send(reqwest::Method::GET, Some(key), &[], Vec::new()).await?;
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        if !response.status().is_success() {
+            return Err(s3_error(response, "get").await);
+        }
+        let bytes = response.bytes().await.map_err(|e| Error::Network(format!("S3 get failed: {}", e)))?;
+        Ok(Some(bytes.to_vec()))
+    }
+
+    async fn delete(&self, key: &str) -> hal9_core::Result<()> {
+        let response = self.send(reqwest::Method::DELETE, Some(key), &[], Vec::new()).await?;
+        if !response.status().is_success() && response.status() != reqwest::StatusCode::NOT_FOUND {
+            return Err(s3_error(response, "delete").await);
+        }
+        Ok(())
+    }
+
+    async fn list(&self, prefix: &str) -> hal9_core::Result<Vec<String>> {
+        let prefix = format!("{}{}", self.prefix, prefix);
+        let mut keys = Vec::new();
+        let mut token: Option<String> = None;
+        loop {
+            let mut query = vec![("list-type", "2"), ("prefix", prefix.as_str())];
+            if let Some(token) = &token {
+                query.push(("continuation-token", token.as_str()));
+            }
+            let response = self.send(reqwest::Method::GET, None, &query, Vec::new()).await?;
+            if !response.status().is_success() {
+                return Err(s3_error(response, "list").await);
+            }
+            let body = response.text().await.map_err(|e| Error::Network(format!("S3 list failed: {}", e)))?;
+            keys.extend(
+                xml_values(&body, "Key")
+                    .into_iter()
+                    .filter_map(|key| key.strip_prefix(&self.prefix).map(str::to_string)),
+            );
+            token = match xml_values(&body, "IsTruncated").first().map(String::as_str) {
+                Some("true") => xml_values(&body, "NextContinuationToken").pop(),
+                _ => None,
+            };
+            if token.is_none() {
+                break;
+            }
+        }
+        Ok(keys)
+    }
+}
+
+/// What one tiering run did
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TieringReport {
+    pub ran_at: DateTime<Utc>,
+    /// Entries moved to the cold tier
+    pub moved: usize,
+    /// Candidates that changed or went away before they were moved
+    pub skipped: usize,
+    /// Candidates that failed to move and stay hot
+    pub failed: usize,
+    /// Cold objects no entry referred to, deleted
+    pub orphans_deleted: u64,
+}
+
+/// Tiering settings, the size of each tier and the last run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TieringStatus {
+    pub enabled: bool,
+    #[serde(flatten)]
+    pub usage: TierUsage,
+    /// Reads served from the cold tier and promotions since startup
+    pub cold_tier: Option<ColdTierStats>,
+    pub last_run: Option<TieringReport>,
+}
+
+/// Moves idle memory entries to the cold tier
+pub struct TieringJob {
+    config: MemoryTieringConfig,
+    store: Arc<SqliteMemoryStore>,
+    last_run: Mutex<Option<TieringReport>>,
+    /// One run at a time
+    running: tokio::sync::Mutex<()>,
+}
+
+impl TieringJob {
+    pub fn new(config: MemoryTieringConfig, store: Arc<SqliteMemoryStore>) -> Self {
+        Self {
+            config,
+            store,
+            last_run: Mutex::new(None),
+            running: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    /// Delete orphaned cold objects, then move the entries due for the cold
+    /// tier at `now`
+    pub async fn run(&self, now: DateTime<Utc>) -> ServerResult<TieringReport> {
+        let _running = self.running.lock().await;
+        let mut report = TieringReport {
+            ran_at: now,
+            ..Default::default()
+        };
+        if self.store.cold_tier().is_none() {
+            return Err(ServerError::ConfigError("No cold tier is configured".to_string()));
+        }
+
+        report.orphans_deleted = self.store.reconcile_cold().await.map_err(internal)?;
+
+        let idle_since = now - chrono::Duration::days(self.config.cold_after_days as i64);
+        let candidates = self.store
+            .tiering_candidates(idle_since, self.config.min_importance, self.config.batch_size)
+            .await
+            .map_err(internal)?;
+        let pause = (self.config.moves_per_second > 0)
+            .then(|| Duration::from_secs_f64(1.0 / self.config.moves_per_second as f64));
+        for (i, id) in candidates.into_iter().enumerate() {
+            if let (Some(pause), true) = (pause, i > 0) {
+                tokio::time::sleep(pause).await;
+            }
+            match self.store.demote(id).await {
+                Ok(true) => report.moved += 1,
+                Ok(false) => report.skipped += 1,
+                Err(e) => {
+                    warn!("Failed to move memory {} to the cold tier: {}", id, e);
+                    report.failed += 1;
+                }
+            }
+        }
+
+        *self.last_run.lock() = Some(report.clone());
+        Ok(report)
+    }
+
+    pub async fn status(&self) -> ServerResult<TieringStatus> {
+        Ok(TieringStatus {
+            enabled: self.config.enabled,
+            usage: self.store.tier_usage().await.map_err(internal)?,
+            cold_tier: self.store.cold_tier().map(|cold| cold.stats()),
+            last_run: self.last_run.lock().clone(),
+        })
+    }
+}
+
+fn internal(e: Error) -> ServerError {
+    ServerError::Internal(e.to_string())
+}
+
+/// Run the tiering job every `memory.tiering.interval_secs`, except while
+/// the server is read-only
+pub async fn tiering_task(job: Arc<TieringJob>, read_only: Arc<ReadOnlyGate>) {
+    let mut interval = tokio::time::interval(Duration::from_secs(job.config.interval_secs.max(1)));
+    loop {
+        interval.tick().await;
+        if read_only.is_active() {
+            debug!("Skipping memory tiering while read-only");
+            continue;
+        }
+        match job.run(Utc::now()).await {
+            Ok(report) if report.moved == 0 && report.orphans_deleted == 0 => {
+                debug!("Memory tiering found nothing to move")
+            }
+            Ok(report) => info!(
+                "Memory tiering moved {} entries to the cold tier and deleted {} orphaned objects",
+                report.moved, report.orphans_deleted
+            ),
+            Err(e) => error!("Memory tiering failed: {}", e),
+        }
+    }
+}
@@ -1,6 +1,6 @@
 //! Metrics collection and monitoring
 
-use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 use dashmap::DashMap;
@@ -27,6 +27,9 @@ pub struct Metrics {
     pub tokens_completion: AtomicU64,
     pub tokens_total: AtomicU64,
     
+    // Last successful Claude response, proof of provider reachability
+    pub provider_last_success: Arc<parking_lot::RwLock<Option<Instant>>>,
+    
     // Cost metrics
     pub cost_hourly: Arc<parking_lot::RwLock<f64>>,
     pub cost_daily: Arc<parking_lot::RwLock<f64>>,
@@ -35,6 +38,66 @@ pub struct Metrics {
     // Error tracking
     pub errors_by_type: Arc<DashMap<String, AtomicU64>>,
     
+    // Failed peer connections by reason (network, handshake, auth)
+    pub peer_connection_failures: Arc<DashMap<String, AtomicU64>>,
+    
+    // Inter-server message sizes before and after compression
+    pub transport: TransportCounters,
+    
+    // WASM plugin calls, host function calls and resource usage by plugin
+    pub plugins: Arc<DashMap<String, PluginCounters>>,
+    
+    // Warm pool checkouts, recycling and instantiation latency by neuron type
+    pub warm_pools: Arc<DashMap<String, WarmPoolCounters>>,
+    
+    // Local model batch sizes, queue latency and deadline bypasses by model
+    pub local_batches: Arc<DashMap<String, LocalBatchCounters>>,
+    
+    // Response validation outcomes keyed by "validator:passed|failed"
+    pub validation_results: Arc<DashMap<String, AtomicU64>>,
+    pub validation_retries: AtomicU64,
+    
+    // Recovery playbook steps keyed by "step:outcome"
+    pub recovery_steps: Arc<DashMap<String, AtomicU64>>,
+    
+    // Speculative child signals by outcome (started, confirmed, cancelled)
+    // and the dollars the cancelled ones cost
+    pub speculation: Arc<DashMap<String, AtomicU64>>,
+    pub speculation_wasted: Arc<parking_lot::RwLock<f64>>,
+    
+    // Concurrency limiters by neuron, read for queue depth and overflow counts
+    pub neuron_queues: Arc<DashMap<String, Arc<crate::concurrency::ConcurrencyLimiter>>>,
+    
+    // Set while distributed rate limits fall back to local-only buckets
+    pub rate_limit_degraded: AtomicBool,
+    
+    // Databases by name, read for connection usage and write contention
+    pub database_pools: Arc<DashMap<String, Arc<dyn crate::database_runtime::PoolStatsSource>>>,
+    
+    // Layer pauses, read for held and spilled signal counts
+    pub layer_gate: Arc<parking_lot::RwLock<Option<Arc<crate::layer_pause::LayerGate>>>>,
+    
+    // Read-only mode, read for its state and held or skipped memory writes
+    pub read_only: Arc<parking_lot::RwLock<Option<Arc<crate::read_only::ReadOnlyGate>>>>,
+    
+    // Signal traffic between layers, read for per-boundary compression
+    pub layer_traffic: Arc<parking_lot::RwLock<Option<Arc<hal9_core::consciousness::LayerTraffic>>>>,
+    
+    // Replica load signals, read for autoscaling gauges
+    pub autoscaler: Arc<parking_lot::RwLock<Option<Arc<crate::scaling::Autoscaler>>>>,
+    
+    // Latency SLOs fed by layer latencies and failures
+    pub slo: Arc<parking_lot::RwLock<Option<Arc<crate::slo::SloTracker>>>>,
+    
+    // Kafka and webhook sources, read for throughput, lag and outcomes
+    pub ingestion: Arc<parking_lot::RwLock<Option<Arc<crate::ingestion::Ingestion>>>>,
+    
+    // Cold memory tier, read for cold reads and promotions
+    pub cold_tier: Arc<parking_lot::RwLock<Option<Arc<hal9_core::memory::ColdTier>>>>,
+    
+    // Genius game WebSocket connections, read for queue depth and lag
+    pub game_connections: Arc<parking_lot::RwLock<Option<Arc<crate::genius_game::GameConnections>>>>,
+    
     // Memory usage
     pub memory_usage_bytes: AtomicU64,
     
@@ -56,10 +119,32 @@ impl Default for Metrics {
             tokens_prompt: AtomicU64::new(0),
             tokens_completion: AtomicU64::new(0),
             tokens_total: AtomicU64::new(0),
+            provider_last_success: Arc::new(parking_lot::RwLock::new(None)),
             cost_hourly: Arc::new(parking_lot::RwLock::new(0.0)),
             cost_daily: Arc::new(parking_lot::RwLock::new(0.0)),
             cost_total: Arc::new(parking_lot::RwLock::new(0.0)),
             errors_by_type: Arc::new(DashMap::new()),
+            peer_connection_failures: Arc::new(DashMap::new()),
+            transport: TransportCounters::default(),
+            plugins: Arc::new(DashMap::new()),
+            warm_pools: Arc::new(DashMap::new()),
+            local_batches: Arc::new(DashMap::new()),
+            validation_results: Arc::new(DashMap::new()),
+            validation_retries: AtomicU64::new(0),
+            recovery_steps: Arc::new(DashMap::new()),
+            speculation: Arc::new(DashMap::new()),
+            speculation_wasted: Arc::new(parking_lot::RwLock::new(0.0)),
+            neuron_queues: Arc::new(DashMap::new()),
+            rate_limit_degraded: AtomicBool::new(false),
+            database_pools: Arc::new(DashMap::new()),
+            layer_gate: Arc::new(parking_lot::RwLock::new(None)),
+            read_only: Arc::new(parking_lot::RwLock::new(None)),
+            layer_traffic: Arc::new(parking_lot::RwLock::new(None)),
+            autoscaler: Arc::new(parking_lot::RwLock::new(None)),
+            slo: Arc::new(parking_lot::RwLock::new(None)),
+            ingestion: Arc::new(parking_lot::RwLock::new(None)),
+            cold_tier: Arc::new(parking_lot::RwLock::new(None)),
+            game_connections: Arc::new(parking_lot::RwLock::new(None)),
             memory_usage_bytes: AtomicU64::new(0),
             start_time: Instant::now(),
         }
@@ -93,6 +178,17 @@ impl Metrics {
             .entry(layer.to_string())
             .or_default()
             .push(latency);
+        if let Some(slo) = self.slo.read().as_ref() {
+            slo.record_layer(layer, chrono::Utc::now(), latency, true);
+        }
+    }
+    
+    /// Record how long a layer took to fail a signal; counts against the
+    /// layer's SLOs only
+    pub fn record_failed_latency(&self, layer: &str, latency: Duration) {
+        if let Some(slo) = self.slo.read().as_ref() {
+            slo.record_layer(layer, chrono::Utc::now(), latency, false);
+        }
     }
     
     /// Update active neuron count
@@ -129,7 +225,17 @@ impl Metrics {
         self.tokens_completion.fetch_add(completion_tokens as u64, Ordering::Relaxed);
         self.tokens_total.fetch_add((prompt_tokens + completion_tokens) as u64, Ordering::Relaxed);
     }
-    
+
+    /// Record a successful Claude response
+    pub fn record_provider_success(&self) {
+        *self.provider_last_success.write() = Some(Instant::now());
+    }
+
+    /// Time since the last successful Claude response
+    pub fn provider_success_age(&self) -> Option<Duration> {
+        self.provider_last_success.read().map(|at| at.elapsed())
+    }
+
     /// Update cost metrics
     pub fn update_cost_metrics(&self, hourly: f64, daily: f64, total: f64) {
         *self.cost_hourly.write() = hourly;
@@ -145,6 +251,195 @@ impl Metrics {
             .fetch_add(1, Ordering::Relaxed);
     }
     
+    /// Record a failed connection to or from a peer server
+    pub fn record_peer_connection_failure(&self, reason: &str) {
+        self.peer_connection_failures
+            .entry(reason.to_string())
+            .or_insert_with(|| AtomicU64::new(0))
+            .fetch_add(1, Ordering::Relaxed);
+    }
+    
+    /// Record a message sent to a peer: its body size before compression
+    /// and the bytes that went on the wire
+    pub fn record_transport_sent(&self, uncompressed: usize, wire: usize, compressed: bool) {
+        self.transport.sent.record(uncompressed, wire, compressed);
+    }
+    
+    /// Record a message received from a peer
+    pub fn record_transport_received(&self, uncompressed: usize, wire: usize, compressed: bool) {
+        self.transport.received.record(uncompressed, wire, compressed);
+    }
+    
+    /// Record signals coalesced into one message to a peer
+    pub fn record_transport_batch(&self, signals: usize) {
+        self.transport.batches_sent.fetch_add(1, Ordering::Relaxed);
+        self.transport.batched_signals.fetch_add(signals as u64, Ordering::Relaxed);
+    }
+    
+    /// Record a call into a plugin's exported function and how it ended
+    pub fn record_plugin_call(&self, plugin: &str, function: &str, elapsed: Duration, outcome: PluginCallOutcome) {
+        self.plugins.entry(plugin.to_string()).or_default().record_call(function, elapsed, outcome);
+    }
+    
+    /// Record a plugin calling a host function
+    pub fn record_plugin_host_call(&self, plugin: &str, host_function: &str) {
+        self.plugins.entry(plugin.to_string()).or_default()
+            .host_calls
+            .entry(host_function.to_string())
+            .or_insert_with(|| AtomicU64::new(0))
+            .fetch_add(1, Ordering::Relaxed);
+    }
+    
+    /// Set a plugin's current linear memory and table size
+    pub fn set_plugin_usage(&self, plugin: &str, memory_bytes: u64, table_elements: u64) {
+        let counters = self.plugins.entry(plugin.to_string()).or_default();
+        counters.memory_bytes.store(memory_bytes, Ordering::Relaxed);
+        counters.table_elements.store(table_elements, Ordering::Relaxed);
+    }
+    
+    /// Counters of one plugin, if it was ever called
+    pub fn plugin_stats(&self, plugin: &str) -> Option<PluginStats> {
+        self.plugins.get(plugin).map(|counters| counters.stats())
+    }
+    
+    /// Record a warm pool checkout served from an idle instance (a hit) or
+    /// by instantiating one on demand (a miss)
+    pub fn record_warm_pool_checkout(&self, neuron_type: &str, hit: bool) {
+        let counters = self.warm_pools.entry(neuron_type.to_string()).or_default();
+        if hit {
+            counters.hits.fetch_add(1, Ordering::Relaxed);
+        } else {
+            counters.misses.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+    
+    /// Record how long creating a pooled instance took
+    pub fn record_warm_pool_instantiation(&self, neuron_type: &str, elapsed: Duration) {
+        self.warm_pools.entry(neuron_type.to_string()).or_default().instantiation.record(elapsed);
+    }
+    
+    /// Record idle instances shut down for exceeding the idle TTL
+    pub fn record_warm_pool_recycled(&self, neuron_type: &str, count: u64) {
+        self.warm_pools.entry(neuron_type.to_string()).or_default().recycled.fetch_add(count, Ordering::Relaxed);
+    }
+    
+    /// Set the idle instances in a warm pool
+    pub fn set_warm_pool_idle(&self, neuron_type: &str, idle: u64) {
+        self.warm_pools.entry(neuron_type.to_string()).or_default().idle.store(idle, Ordering::Relaxed);
+    }
+    
+    /// Counters of one warm pool, if it was ever used
+    pub fn warm_pool_stats(&self, neuron_type: &str) -> Option<WarmPoolStats> {
+        self.warm_pools.get(neuron_type).map(|counters| counters.stats())
+    }
+    
+    /// Record a batch of requests submitted to a local model
+    pub fn record_local_batch(&self, model: &str, size: usize) {
+        self.local_batches.entry(model.to_string()).or_default().record_batch(size);
+    }
+    
+    /// Record how long a request waited for its local model batch
+    pub fn record_local_queue_latency(&self, model: &str, waited: Duration) {
+        self.local_batches.entry(model.to_string()).or_default().queue_latency.record(waited);
+    }
+    
+    /// Record a request sent to a local model alone, too close to its
+    /// signal's deadline to wait for a batch
+    pub fn record_local_bypass(&self, model: &str) {
+        self.local_batches.entry(model.to_string()).or_default().bypassed.fetch_add(1, Ordering::Relaxed);
+    }
+    
+    /// Record one validator's verdict on a response
+    pub fn record_validation(&self, validator: &str, passed: bool) {
+        let outcome = if passed { "passed" } else { "failed" };
+        self.validation_results
+            .entry(format!("{}:{}", validator, outcome))
+            .or_insert_with(|| AtomicU64::new(0))
+            .fetch_add(1, Ordering::Relaxed);
+    }
+    
+    /// Record a neuron being re-prompted after failed validation
+    pub fn record_validation_retry(&self) {
+        self.validation_retries.fetch_add(1, Ordering::Relaxed);
+    }
+    
+    /// Record a speculative child signal starting, or being confirmed or
+    /// cancelled once its parent finished
+    pub fn record_speculation(&self, outcome: &str) {
+        self.speculation
+            .entry(outcome.to_string())
+            .or_insert_with(|| AtomicU64::new(0))
+            .fetch_add(1, Ordering::Relaxed);
+    }
+    
+    /// Record the cost of a cancelled speculative child
+    pub fn record_speculation_wasted(&self, dollars: f64) {
+        *self.speculation_wasted.write() += dollars;
+    }
+    
+    /// Record one recovery step attempted on a failed signal
+    pub fn record_recovery_step(&self, step: &str, outcome: &str) {
+        self.recovery_steps
+            .entry(format!("{}:{}", step, outcome))
+            .or_insert_with(|| AtomicU64::new(0))
+            .fetch_add(1, Ordering::Relaxed);
+    }
+    
+    /// Track a neuron's queue in snapshots
+    pub fn register_neuron_queue(&self, neuron_id: &str, limiter: Arc<crate::concurrency::ConcurrencyLimiter>) {
+        self.neuron_queues.insert(neuron_id.to_string(), limiter);
+    }
+    
+    /// Track a database's pools in snapshots
+    pub fn register_database_pool(&self, name: &str, pools: Arc<dyn crate::database_runtime::PoolStatsSource>) {
+        self.database_pools.insert(name.to_string(), pools);
+    }
+    
+    /// Track paused layers in snapshots
+    pub fn set_layer_gate(&self, gate: Arc<crate::layer_pause::LayerGate>) {
+        *self.layer_gate.write() = Some(gate);
+    }
+    
+    /// Track read-only mode in snapshots
+    pub fn set_read_only(&self, gate: Arc<crate::read_only::ReadOnlyGate>) {
+        *self.read_only.write() = Some(gate);
+    }
+    
+    /// Track layer compression in snapshots
+    pub fn set_layer_traffic(&self, traffic: Arc<hal9_core::consciousness::LayerTraffic>) {
+        *self.layer_traffic.write() = Some(traffic);
+    }
+    
+    /// Track replica load signals in snapshots
+    pub fn set_autoscaler(&self, autoscaler: Arc<crate::scaling::Autoscaler>) {
+        *self.autoscaler.write() = Some(autoscaler);
+    }
+    
+    /// Feed layer latencies and failures to SLO tracking
+    pub fn set_slo_tracker(&self, slo: Arc<crate::slo::SloTracker>) {
+        *self.slo.write() = Some(slo);
+    }
+    
+    /// Track ingestion sources in snapshots
+    pub fn set_ingestion(&self, ingestion: Arc<crate::ingestion::Ingestion>) {
+        *self.ingestion.write() = Some(ingestion);
+    }
+    
+    /// Track reads of the cold memory tier in snapshots
+    pub fn set_cold_tier(&self, cold: Arc<hal9_core::memory::ColdTier>) {
+        *self.cold_tier.write() = Some(cold);
+    }
+    
+    /// Track the genius game's outbound queues in snapshots
+    pub fn set_game_connections(&self, connections: Arc<crate::genius_game::GameConnections>) {
+        *self.game_connections.write() = Some(connections);
+    }
+    
+    /// Flag whether distributed rate limits are enforced locally only
+    pub fn set_rate_limit_degraded(&self, degraded: bool) {
+        self.rate_limit_degraded.store(degraded, Ordering::Relaxed);
+    }
+    
     /// Update memory usage
     pub fn update_memory_usage(&self) {
         // Simple memory estimation - in production, use proper memory profiling
@@ -205,6 +500,72 @@ impl Metrics {
             );
         }
         
+        let mut peer_connection_failures = std::collections::HashMap::new();
+        for entry in self.peer_connection_failures.iter() {
+            peer_connection_failures.insert(
+                entry.key().clone(),
+                entry.value().load(Ordering::Relaxed)
+            );
+        }
+        
+        let mut validation_results = std::collections::HashMap::new();
+        for entry in self.validation_results.iter() {
+            validation_results.insert(
+                entry.key().clone(),
+                entry.value().load(Ordering::Relaxed)
+            );
+        }
+        
+        let recovery_steps = self.recovery_steps.iter()
+            .map(|entry| (entry.key().clone(), entry.value().load(Ordering::Relaxed)))
+            .collect();
+        
+        let speculation = self.speculation.iter()
+            .map(|entry| (entry.key().clone(), entry.value().load(Ordering::Relaxed)))
+            .collect();
+        
+        let neuron_queues = self.neuron_queues.iter()
+            .map(|entry| (entry.key().clone(), entry.value().stats()))
+            .collect();
+        
+        let database_pools = self.database_pools.iter()
+            .map(|entry| (entry.key().clone(), entry.value().pool_stats()))
+            .collect();
+        
+        let (paused_layers, layer_pause_spilled) = match self.layer_gate.read().as_ref() {
+            Some(gate) => (
+                gate.paused().into_iter().map(|p| (p.pause.layer, p.held)).collect(),
+                gate.spilled(),
+            ),
+            None => Default::default(),
+        };
+        
+        let read_only = self.read_only.read().as_ref().map(|gate| gate.status());
+        
+        let (layer_compression, backward_signals) = self.layer_traffic.read().as_ref()
+            .map(|traffic| (traffic.snapshot().boundaries, traffic.backward_totals()))
+            .unwrap_or_default();
+        
+        let autoscaling = self.autoscaler.read().as_ref().map(|autoscaler| autoscaler.load());
+        
+        let ingestion = self.ingestion.read().as_ref().map(|ingestion| ingestion.status()).unwrap_or_default();
+        
+        let cold_tier = self.cold_tier.read().as_ref().map(|cold| cold.stats());
+        
+        let game_connections = self.game_connections.read().as_ref().map(|connections| connections.stats());
+        
+        let plugins = self.plugins.iter()
+            .map(|entry| (entry.key().clone(), entry.value().stats()))
+            .collect();
+        
+        let warm_pools = self.warm_pools.iter()
+            .map(|entry| (entry.key().clone(), entry.value().stats()))
+            .collect();
+        
+        let local_batches = self.local_batches.iter()
+            .map(|entry| (entry.key().clone(), entry.value().stats()))
+            .collect();
+        
         MetricsSnapshot {
             uptime_seconds: self.start_time.elapsed().as_secs(),
             signals_sent: self.signals_sent.load(Ordering::Relaxed),
@@ -223,6 +584,28 @@ impl Metrics {
             cost_daily: *self.cost_daily.read(),
             cost_total: *self.cost_total.read(),
             errors_by_type,
+            peer_connection_failures,
+            transport: self.transport.stats(),
+            plugins,
+            warm_pools,
+            local_batches,
+            validation_results,
+            validation_retries: self.validation_retries.load(Ordering::Relaxed),
+            recovery_steps,
+            speculation,
+            speculation_wasted: *self.speculation_wasted.read(),
+            neuron_queues,
+            rate_limit_degraded: self.rate_limit_degraded.load(Ordering::Relaxed),
+            database_pools,
+            paused_layers,
+            layer_pause_spilled,
+            read_only,
+            layer_compression,
+            backward_signals,
+            autoscaling,
+            ingestion,
+            cold_tier,
+            game_connections,
             memory_usage_mb: self.memory_usage_bytes.load(Ordering::Relaxed) as f64 / (1024.0 * 1024.0),
         }
     }
@@ -258,6 +641,64 @@ pub struct MetricsSnapshot {
     pub cost_daily: f64,
     pub cost_total: f64,
     pub errors_by_type: std::collections::HashMap<String, u64>,
+    #[serde(default)]
+    pub peer_connection_failures: std::collections::HashMap<String, u64>,
+    #[serde(default)]
+    pub transport: TransportStats,
+    /// WASM plugin counters by plugin name
+    #[serde(default)]
+    pub plugins: std::collections::HashMap<String, PluginStats>,
+    /// Warm pool counters by neuron type
+    #[serde(default)]
+    pub warm_pools: std::collections::HashMap<String, WarmPoolStats>,
+    /// Local model batching by model
+    #[serde(default)]
+    pub local_batches: std::collections::HashMap<String, LocalBatchStats>,
+    #[serde(default)]
+    pub validation_results: std::collections::HashMap<String, u64>,
+    #[serde(default)]
+    pub validation_retries: u64,
+    /// Recovery playbook steps keyed by "step:outcome"
+    #[serde(default)]
+    pub recovery_steps: std::collections::HashMap<String, u64>,
+    /// Speculative child signals by outcome
+    #[serde(default)]
+    pub speculation: std::collections::HashMap<String, u64>,
+    /// Dollars spent on cancelled speculative children
+    #[serde(default)]
+    pub speculation_wasted: f64,
+    #[serde(default)]
+    pub neuron_queues: std::collections::HashMap<String, crate::concurrency::ConcurrencyStats>,
+    #[serde(default)]
+    pub rate_limit_degraded: bool,
+    #[serde(default)]
+    pub database_pools: std::collections::HashMap<String, hal9_core::sqlite::PoolStats>,
+    /// Signals held per paused layer
+    #[serde(default)]
+    pub paused_layers: std::collections::HashMap<String, usize>,
+    #[serde(default)]
+    pub layer_pause_spilled: u64,
+    /// Read-only mode and the memory writes it held or skipped
+    #[serde(default)]
+    pub read_only: Option<crate::read_only::ReadOnlyStatus>,
+    /// Compression across each boundary that carried traffic in the window
+    #[serde(default)]
+    pub layer_compression: Vec<hal9_core::consciousness::BoundaryFlow>,
+    /// Backward signals that crossed each boundary since startup
+    #[serde(default)]
+    pub backward_signals: std::collections::BTreeMap<String, u64>,
+    /// Pending signals, oldest pending age and slot saturation
+    #[serde(default)]
+    pub autoscaling: Option<crate::scaling::LoadSignals>,
+    /// Throughput, lag and outcomes of each ingestion source
+    #[serde(default)]
+    pub ingestion: Vec<crate::ingestion::SourceStatus>,
+    /// Reads served from the cold memory tier and promotions back
+    #[serde(default)]
+    pub cold_tier: Option<hal9_core::memory::ColdTierStats>,
+    /// Outbound queues of the genius game's WebSocket connections
+    #[serde(default)]
+    pub game_connections: Option<crate::genius_game::GameConnectionStats>,
     pub memory_usage_mb: f64,
 }
 
@@ -268,4 +709,322 @@ pub struct LatencyStats {
     pub avg_ms: f64,
     pub min_ms: f64,
     pub max_ms: f64,
-}
\ No newline at end of file
+}
+
+/// Message and byte counts in one direction of the inter-server transport
+#[derive(Default)]
+pub struct TransportDirection {
+    pub messages: AtomicU64,
+    pub compressed_messages: AtomicU64,
+    pub uncompressed_bytes: AtomicU64,
+    pub wire_bytes: AtomicU64,
+}
+
+impl TransportDirection {
+    fn record(&self, uncompressed: usize, wire: usize, compressed: bool) {
+        self.messages.fetch_add(1, Ordering::Relaxed);
+        if compressed {
+            self.compressed_messages.fetch_add(1, Ordering::Relaxed);
+        }
+        self.uncompressed_bytes.fetch_add(uncompressed as u64, Ordering::Relaxed);
+        self.wire_bytes.fetch_add(wire as u64, Ordering::Relaxed);
+    }
+    
+    fn stats(&self) -> TransportDirectionStats {
+        TransportDirectionStats {
+            messages: self.messages.load(Ordering::Relaxed),
+            compressed_messages: self.compressed_messages.load(Ordering::Relaxed),
+            uncompressed_bytes: self.uncompressed_bytes.load(Ordering::Relaxed),
+            wire_bytes: self.wire_bytes.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Inter-server transport counters
+#[derive(Default)]
+pub struct TransportCounters {
+    pub sent: TransportDirection,
+    pub received: TransportDirection,
+    pub batches_sent: AtomicU64,
+    pub batched_signals: AtomicU64,
+}
+
+impl TransportCounters {
+    fn stats(&self) -> TransportStats {
+        TransportStats {
+            sent: self.sent.stats(),
+            received: self.received.stats(),
+            batches_sent: self.batches_sent.load(Ordering::Relaxed),
+            batched_signals: self.batched_signals.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Message and byte counts in one direction
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TransportDirectionStats {
+    pub messages: u64,
+    pub compressed_messages: u64,
+    /// Message bodies as serialized
+    pub uncompressed_bytes: u64,
+    /// Message bodies as sent, after compression
+    pub wire_bytes: u64,
+}
+
+impl TransportDirectionStats {
+    /// Wire bytes per serialized byte; 1.0 when nothing was compressed
+    pub fn compression_ratio(&self) -> f64 {
+        if self.uncompressed_bytes == 0 {
+            1.0
+        } else {
+            self.wire_bytes as f64 / self.uncompressed_bytes as f64
+        }
+    }
+}
+
+/// Inter-server transport traffic
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TransportStats {
+    pub sent: TransportDirectionStats,
+    pub received: TransportDirectionStats,
+    /// Batch messages sent and the signals they carried
+    pub batches_sent: u64,
+    pub batched_signals: u64,
+}
+
+/// Upper bounds, in seconds, of the plugin execution and warm pool
+/// instantiation time buckets
+pub const PLUGIN_DURATION_BUCKETS: [f64; 8] = [0.001, 0.005, 0.01, 0.05, 0.1, 0.5, 1.0, 5.0];
+
+/// A resource budget a plugin call can run out of
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PluginBudget {
+    /// Fuel, or the epoch deadline
+    Cpu,
+    /// Linear memory or table growth
+    Memory,
+}
+
+impl PluginBudget {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PluginBudget::Cpu => "cpu",
+            PluginBudget::Memory => "memory",
+        }
+    }
+}
+
+/// How a plugin call ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PluginCallOutcome {
+    Ok,
+    Error,
+    /// Terminated for exceeding a budget; also counted as an error
+    Exhausted(PluginBudget),
+}
+
+/// Durations bucketed by `PLUGIN_DURATION_BUCKETS`, e.g. the execution
+/// times of one plugin function
+#[derive(Default)]
+pub(crate) struct DurationHistogram {
+    /// Calls per bucket of `PLUGIN_DURATION_BUCKETS`, not cumulative
+    buckets: [AtomicU64; PLUGIN_DURATION_BUCKETS.len()],
+    count: AtomicU64,
+    sum_micros: AtomicU64,
+}
+
+impl DurationHistogram {
+    pub(crate) fn record(&self, elapsed: Duration) {
+        let seconds = elapsed.as_secs_f64();
+        if let Some(bucket) = PLUGIN_DURATION_BUCKETS.iter().position(|bound| seconds <= *bound) {
+            self.buckets[bucket].fetch_add(1, Ordering::Relaxed);
+        }
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.sum_micros.fetch_add(elapsed.as_micros() as u64, Ordering::Relaxed);
+    }
+    
+    pub(crate) fn stats(&self) -> HistogramStats {
+        let mut cumulative = 0;
+        let buckets = self.buckets.iter()
+            .map(|bucket| {
+                cumulative += bucket.load(Ordering::Relaxed);
+                cumulative
+            })
+            .collect();
+        HistogramStats {
+            count: self.count.load(Ordering::Relaxed),
+            sum_seconds: self.sum_micros.load(Ordering::Relaxed) as f64 / 1_000_000.0,
+            buckets,
+        }
+    }
+}
+
+/// Counters of one WASM plugin
+#[derive(Default)]
+pub struct PluginCounters {
+    pub invocations: AtomicU64,
+    pub errors: AtomicU64,
+    pub cpu_exhaustions: AtomicU64,
+    pub memory_exhaustions: AtomicU64,
+    pub host_calls: DashMap<String, AtomicU64>,
+    pub memory_bytes: AtomicU64,
+    pub table_elements: AtomicU64,
+    execution: DashMap<String, DurationHistogram>,
+}
+
+impl PluginCounters {
+    fn record_call(&self, function: &str, elapsed: Duration, outcome: PluginCallOutcome) {
+        self.invocations.fetch_add(1, Ordering::Relaxed);
+        match outcome {
+            PluginCallOutcome::Ok => {}
+            PluginCallOutcome::Error => {
+                self.errors.fetch_add(1, Ordering::Relaxed);
+            }
+            PluginCallOutcome::Exhausted(budget) => {
+                self.errors.fetch_add(1, Ordering::Relaxed);
+                match budget {
+                    PluginBudget::Cpu => self.cpu_exhaustions.fetch_add(1, Ordering::Relaxed),
+                    PluginBudget::Memory => self.memory_exhaustions.fetch_add(1, Ordering::Relaxed),
+                };
+            }
+        }
+        self.execution.entry(function.to_string()).or_default().record(elapsed);
+    }
+    
+    fn stats(&self) -> PluginStats {
+        PluginStats {
+            invocations: self.invocations.load(Ordering::Relaxed),
+            errors: self.errors.load(Ordering::Relaxed),
+            cpu_exhaustions: self.cpu_exhaustions.load(Ordering::Relaxed),
+            memory_exhaustions: self.memory_exhaustions.load(Ordering::Relaxed),
+            host_calls: self.host_calls.iter()
+                .map(|entry| (entry.key().clone(), entry.value().load(Ordering::Relaxed)))
+                .collect(),
+            memory_bytes: self.memory_bytes.load(Ordering::Relaxed),
+            table_elements: self.table_elements.load(Ordering::Relaxed),
+            execution: self.execution.iter()
+                .map(|entry| (entry.key().clone(), entry.value().stats()))
+                .collect(),
+        }
+    }
+}
+
+/// Execution time distribution
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct HistogramStats {
+    pub count: u64,
+    pub sum_seconds: f64,
+    /// Cumulative counts per bound of `PLUGIN_DURATION_BUCKETS`
+    pub buckets: Vec<u64>,
+}
+
+/// Counters of one WASM plugin
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PluginStats {
+    pub invocations: u64,
+    /// Failed calls, including those terminated for exceeding a budget
+    pub errors: u64,
+    pub cpu_exhaustions: u64,
+    pub memory_exhaustions: u64,
+    /// Calls to host functions by function name
+    pub host_calls: std::collections::HashMap<String, u64>,
+    /// Current linear memory size
+    pub memory_bytes: u64,
+    /// Current table size
+    pub table_elements: u64,
+    /// Execution time by exported function
+    pub execution: std::collections::HashMap<String, HistogramStats>,
+}
+
+/// Counters of one neuron type's warm pool
+#[derive(Default)]
+pub struct WarmPoolCounters {
+    pub hits: AtomicU64,
+    pub misses: AtomicU64,
+    pub recycled: AtomicU64,
+    pub idle: AtomicU64,
+    instantiation: DurationHistogram,
+}
+
+impl WarmPoolCounters {
+    fn stats(&self) -> WarmPoolStats {
+        WarmPoolStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            recycled: self.recycled.load(Ordering::Relaxed),
+            idle: self.idle.load(Ordering::Relaxed),
+            instantiation: self.instantiation.stats(),
+        }
+    }
+}
+
+/// Counters of one neuron type's warm pool
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct WarmPoolStats {
+    /// Checkouts served by an idle instance
+    pub hits: u64,
+    /// Checkouts that instantiated on demand
+    pub misses: u64,
+    /// Idle instances replaced after the idle TTL
+    pub recycled: u64,
+    /// Current idle instances
+    pub idle: u64,
+    /// Time to create an instance, warm or on demand
+    pub instantiation: HistogramStats,
+}
+
+/// Upper bounds of the local model batch size buckets
+pub const LOCAL_BATCH_SIZE_BUCKETS: [f64; 7] = [1.0, 2.0, 4.0, 8.0, 16.0, 32.0, 64.0];
+
+/// Counters of one local model's batching
+#[derive(Default)]
+pub struct LocalBatchCounters {
+    /// Batches per bucket of `LOCAL_BATCH_SIZE_BUCKETS`, not cumulative
+    sizes: [AtomicU64; LOCAL_BATCH_SIZE_BUCKETS.len()],
+    pub batches: AtomicU64,
+    pub batched_requests: AtomicU64,
+    pub bypassed: AtomicU64,
+    queue_latency: DurationHistogram,
+}
+
+impl LocalBatchCounters {
+    fn record_batch(&self, size: usize) {
+        if let Some(bucket) = LOCAL_BATCH_SIZE_BUCKETS.iter().position(|bound| size as f64 <= *bound) {
+            self.sizes[bucket].fetch_add(1, Ordering::Relaxed);
+        }
+        self.batches.fetch_add(1, Ordering::Relaxed);
+        self.batched_requests.fetch_add(size as u64, Ordering::Relaxed);
+    }
+    
+    fn stats(&self) -> LocalBatchStats {
+        let mut cumulative = 0;
+        let batch_sizes = self.sizes.iter()
+            .map(|bucket| {
+                cumulative += bucket.load(Ordering::Relaxed);
+                cumulative
+            })
+            .collect();
+        LocalBatchStats {
+            batches: self.batches.load(Ordering::Relaxed),
+            batched_requests: self.batched_requests.load(Ordering::Relaxed),
+            batch_sizes,
+            bypassed: self.bypassed.load(Ordering::Relaxed),
+            queue_latency: self.queue_latency.stats(),
+        }
+    }
+}
+
+/// Counters of one local model's batching
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LocalBatchStats {
+    pub batches: u64,
+    /// Requests across all batches
+    pub batched_requests: u64,
+    /// Cumulative batch counts per bound of `LOCAL_BATCH_SIZE_BUCKETS`
+    pub batch_sizes: Vec<u64>,
+    /// Requests sent alone because their deadline was near
+    pub bypassed: u64,
+    /// Time requests waited for their batch to be sent
+    pub queue_latency: HistogramStats,
+}
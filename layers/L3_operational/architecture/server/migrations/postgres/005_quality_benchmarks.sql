@@ -0,0 +1,40 @@
+-- Quality benchmarks and their scores for PostgreSQL
+
+-- Curated signals and how their outputs are graded
+CREATE TABLE IF NOT EXISTS quality_benchmarks (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    neuron VARCHAR(255) NOT NULL,
+    input TEXT NOT NULL,
+    grader TEXT NOT NULL,
+    created_at BIGINT NOT NULL
+);
+
+CREATE INDEX idx_quality_benchmarks_neuron ON quality_benchmarks(neuron);
+
+-- Runs of the benchmarks against one configuration
+CREATE TABLE IF NOT EXISTS quality_runs (
+    id TEXT PRIMARY KEY,
+    triggered_by VARCHAR(50) NOT NULL,
+    config_hash VARCHAR(64) NOT NULL,
+    candidate BOOLEAN NOT NULL DEFAULT FALSE,
+    started_at BIGINT NOT NULL,
+    finished_at BIGINT,
+    mean_score DOUBLE PRECISION,
+    error TEXT
+);
+
+CREATE INDEX idx_quality_runs_started_at ON quality_runs(started_at);
+
+-- Score of each benchmark in a run
+CREATE TABLE IF NOT EXISTS quality_scores (
+    run_id TEXT NOT NULL REFERENCES quality_runs(id) ON DELETE CASCADE,
+    benchmark_id TEXT NOT NULL,
+    neuron VARCHAR(255) NOT NULL,
+    layer VARCHAR(50) NOT NULL,
+    score DOUBLE PRECISION NOT NULL,
+    output TEXT NOT NULL,
+    detail TEXT,
+    
+    PRIMARY KEY (run_id, benchmark_id)
+);
@@ -0,0 +1,13 @@
+-- Staging chains started by shadowed submissions for PostgreSQL
+
+-- A production chain and the staging chain its shadow started
+CREATE TABLE IF NOT EXISTS shadow_chains (
+    production_chain_id TEXT PRIMARY KEY,
+    staging_chain_id TEXT,
+    endpoint VARCHAR(50) NOT NULL,
+    sent_at BIGINT NOT NULL,
+    latency_ms BIGINT NOT NULL,
+    error TEXT
+);
+
+CREATE INDEX idx_shadow_chains_staging_chain_id ON shadow_chains(staging_chain_id);
@@ -0,0 +1,25 @@
+-- Chain templates and the files stored for them for PostgreSQL
+
+-- Every version of each named template
+CREATE TABLE IF NOT EXISTS chain_templates (
+    name VARCHAR(255) NOT NULL,
+    version BIGINT NOT NULL,
+    spec TEXT NOT NULL,
+    created_by TEXT,
+    created_at BIGINT NOT NULL,
+    
+    PRIMARY KEY (name, version)
+);
+
+-- Files given for file variables too large to inline into the content
+CREATE TABLE IF NOT EXISTS template_artifacts (
+    id TEXT PRIMARY KEY,
+    template VARCHAR(255) NOT NULL,
+    version BIGINT NOT NULL,
+    name TEXT NOT NULL,
+    content TEXT NOT NULL,
+    size_bytes BIGINT NOT NULL,
+    created_at BIGINT NOT NULL
+);
+
+CREATE INDEX idx_template_artifacts_template ON template_artifacts(template);
@@ -0,0 +1,41 @@
+-- Quality benchmarks and their scores for SQLite
+
+-- Curated signals and how their outputs are graded
+CREATE TABLE IF NOT EXISTS quality_benchmarks (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    neuron TEXT NOT NULL,
+    input TEXT NOT NULL,
+    grader TEXT NOT NULL,
+    created_at INTEGER NOT NULL
+);
+
+CREATE INDEX idx_quality_benchmarks_neuron ON quality_benchmarks(neuron);
+
+-- Runs of the benchmarks against one configuration
+CREATE TABLE IF NOT EXISTS quality_runs (
+    id TEXT PRIMARY KEY,
+    triggered_by TEXT NOT NULL,
+    config_hash TEXT NOT NULL,
+    candidate INTEGER NOT NULL DEFAULT 0,
+    started_at INTEGER NOT NULL,
+    finished_at INTEGER,
+    mean_score REAL,
+    error TEXT
+);
+
+CREATE INDEX idx_quality_runs_started_at ON quality_runs(started_at);
+
+-- Score of each benchmark in a run
+CREATE TABLE IF NOT EXISTS quality_scores (
+    run_id TEXT NOT NULL,
+    benchmark_id TEXT NOT NULL,
+    neuron TEXT NOT NULL,
+    layer TEXT NOT NULL,
+    score REAL NOT NULL,
+    output TEXT NOT NULL,
+    detail TEXT,
+    
+    PRIMARY KEY (run_id, benchmark_id),
+    FOREIGN KEY (run_id) REFERENCES quality_runs(id) ON DELETE CASCADE
+);
@@ -0,0 +1,13 @@
+-- Staging chains started by shadowed submissions for SQLite
+
+-- A production chain and the staging chain its shadow started
+CREATE TABLE IF NOT EXISTS shadow_chains (
+    production_chain_id TEXT PRIMARY KEY,
+    staging_chain_id TEXT,
+    endpoint TEXT NOT NULL,
+    sent_at INTEGER NOT NULL,
+    latency_ms INTEGER NOT NULL,
+    error TEXT
+);
+
+CREATE INDEX idx_shadow_chains_staging_chain_id ON shadow_chains(staging_chain_id);
@@ -0,0 +1,28 @@
+-- Multi-turn chain conversations for SQLite; login sessions are the sessions table of 001
+
+-- One row per session, with the rolling summary of its turns
+CREATE TABLE IF NOT EXISTS chat_sessions (
+    id TEXT PRIMARY KEY,
+    user_id TEXT,
+    org_id TEXT,
+    summary TEXT NOT NULL,
+    turns INTEGER NOT NULL,
+    created_at INTEGER NOT NULL,
+    last_active_at INTEGER NOT NULL,
+    expires_at INTEGER NOT NULL
+);
+
+CREATE INDEX idx_chat_sessions_user ON chat_sessions(user_id);
+CREATE INDEX idx_chat_sessions_expires ON chat_sessions(expires_at);
+
+-- The latest turns of each session; deleted with their session
+CREATE TABLE IF NOT EXISTS chat_session_turns (
+    session_id TEXT NOT NULL,
+    seq INTEGER NOT NULL,
+    chain_id TEXT NOT NULL,
+    input TEXT NOT NULL,
+    output TEXT NOT NULL,
+    created_at INTEGER NOT NULL,
+    
+    PRIMARY KEY (session_id, seq)
+);
@@ -0,0 +1,6 @@
+-- Organization each template artifact was submitted by, for SQLite, so its
+-- storage is billed to it
+
+ALTER TABLE template_artifacts ADD COLUMN org_id TEXT;
+
+CREATE INDEX idx_template_artifacts_org ON template_artifacts(org_id);
@@ -0,0 +1,743 @@
+//! Scenario files for mock Claude
+//!
+//! A scenario is a file in `claude.scenarios.dir` listing, per neuron, the
+//! prompts it expects in order and what to answer each with. A prompt
+//! matches an expectation when its regex finds a match in the prompt, or in
+//! the value `json_path` selects from the first JSON object in the prompt.
+//! The response is a template: `$1`, `${name}` and `$0` are replaced with
+//! the regex's captures, and `$$` is a literal `$`. An expectation may also
+//! delay its response or fail the call instead.
+//!
+//! Scenarios replay in mock mode only. The one named by `HAL9_MOCK_SCENARIO`,
+//! or else `claude.scenarios.active`, is loaded at startup, and
+//! `PUT /api/v1/admin/mock/scenario` switches to another. A prompt the
+//! scenario does not expect next is answered by the layer's mock responses,
+//! or fails the call with a drift error in strict mode.
+//!
+//! With `claude.scenarios.record_to` set, every exchange of every neuron is
+//! recorded, in any mode, and written there as a scenario on shutdown or
+//! on `POST /api/v1/admin/mock/recording/save`. Recorded prompts match
+//! exactly, except for the UUIDs and timestamps in them, which differ
+//! between runs. Delays are not recorded.
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use parking_lot::{Mutex, RwLock};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::sync::mpsc;
+use tracing::{debug, error, info, warn};
+
+use hal9_core::config::{ClaudeConfig, MockScenarioConfig};
+use hal9_core::{Error, Result};
+
+use crate::claude::{ClaudeInterface, TokenUsage};
+use crate::error::{ServerError, ServerResult};
+
+/// Environment variable naming the scenario to load at startup
+pub const SCENARIO_ENV: &str = "HAL9_MOCK_SCENARIO";
+
+/// Extensions of scenario files, in the order they are looked up
+const EXTENSIONS: &[&str] = &["yaml", "yml", "json"];
+
+/// Regex of expectations without one
+const ANY_PROMPT: &str = "(?s).*";
+
+/// A scenario file
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ScenarioFile {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Fail prompts the scenario does not expect; overrides
+    /// `claude.scenarios.strict`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub strict: Option<bool>,
+    /// Expected prompts of each neuron, in order
+    #[serde(default)]
+    pub neurons: BTreeMap<String, Vec<Expectation>>,
+}
+
+/// A prompt a neuron is expected to send and the answer to it
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Expectation {
+    #[serde(default, rename = "match")]
+    pub matcher: PromptMatch,
+    /// Response template, expanded with the regex's captures
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub response: String,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub delay_ms: u64,
+    /// Fail the call instead of responding
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fault: Option<MockFault>,
+}
+
+/// How a prompt is matched. Without a regex any prompt matches.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PromptMatch {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub regex: Option<String>,
+    /// Path such as `$.task.steps[0]` into the first JSON object in the
+    /// prompt; the regex is matched against the value it selects
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub json_path: Option<String>,
+}
+
+/// Failure returned in place of a response, after the expectation's delay
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum MockFault {
+    /// The provider returned an error
+    Error { message: String },
+    /// The call timed out
+    Timeout,
+}
+
+fn is_zero(value: &u64) -> bool {
+    *value == 0
+}
+
+/// A segment of a JSON path
+#[derive(Debug, Clone, PartialEq)]
+enum PathSegment {
+    Key(String),
+    Index(usize),
+}
+
+/// Parse the `$.key[0].key` subset of JSONPath
+fn parse_json_path(path: &str) -> std::result::Result<Vec<PathSegment>, String> {
+    let mut rest = path
+        .strip_prefix('$')
+        .ok_or_else(|| format!("json_path {} must start with $", path))?;
+    let mut segments = Vec::new();
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix('.') {
+            let end = after.find(['.', '[']).unwrap_or(after.len());
+            if end == 0 {
+                return Err(format!("json_path {} has an empty key", path));
+            }
+            segments.push(PathSegment::Key(after[..end].to_string()));
+            rest = &after[end..];
+        } else if let Some(after) = rest.strip_prefix('[') {
+            let end = after.find(']').ok_or_else(|| format!("json_path {} has an unclosed [", path))?;
+            let index = after[..end]
+                .parse()
+                .map_err(|_| format!("json_path {} indexes with {:?}, not a number", path, &after[..end]))?;
+            segments.push(PathSegment::Index(index));
+            rest = &after[end + 1..];
+        } else {
+            return Err(format!("json_path {} is not of the form $.key[0]", path));
+        }
+    }
+    Ok(segments)
+}
+
+/// The prompt if it is JSON, or else the first JSON object in it
+fn embedded_json(prompt: &str) -> Option<Value> {
+    if let Ok(value) = serde_json::from_str(prompt) {
+        return Some(value);
+    }
+    prompt.match_indices('{').find_map(|(start, _)| {
+        serde_json::Deserializer::from_str(&prompt[start..])
+            .into_iter::<Value>()
+            .next()
+            .and_then(|value| value.ok())
+            .filter(Value::is_object)
+    })
+}
+
+/// Text of the value `path` selects in `prompt`; strings without quotes
+fn select_json(prompt: &str, path: &[PathSegment]) -> Option<String> {
+    let root = embedded_json(prompt)?;
+    let value = path.iter().try_fold(&root, |value, segment| match segment {
+        PathSegment::Key(key) => value.get(key),
+        PathSegment::Index(index) => value.get(index),
+    })?;
+    Some(match value {
+        Value::String(text) => text.clone(),
+        other => other.to_string(),
+    })
+}
+
+/// An expectation ready to match
+struct Step {
+    regex: Regex,
+    json_path: Option<Vec<PathSegment>>,
+    response: String,
+    delay: Duration,
+    fault: Option<MockFault>,
+}
+
+impl Step {
+    fn compile(expectation: Expectation) -> std::result::Result<Self, String> {
+        let pattern = expectation.matcher.regex.as_deref().unwrap_or(ANY_PROMPT);
+        let regex = Regex::new(pattern).map_err(|e| format!("invalid regex: {}", e))?;
+        let json_path = expectation.matcher.json_path.as_deref().map(parse_json_path).transpose()?;
+        Ok(Self {
+            regex,
+            json_path,
+            response: expectation.response,
+            delay: Duration::from_millis(expectation.delay_ms),
+            fault: expectation.fault,
+        })
+    }
+
+    /// The reply to `prompt`, if it matches
+    fn reply(&self, prompt: &str) -> Option<Reply> {
+        let subject = match &self.json_path {
+            Some(path) => select_json(prompt, path)?,
+            None => prompt.to_string(),
+        };
+        let captures = self.regex.captures(&subject)?;
+        let mut response = String::new();
+        captures.expand(&self.response, &mut response);
+        Some(Reply { response, delay: self.delay, fault: self.fault.clone() })
+    }
+
+    fn describe(&self) -> String {
+        match &self.json_path {
+            Some(_) => format!("/{}/ at its json_path", self.regex),
+            None => format!("/{}/", self.regex),
+        }
+    }
+}
+
+/// What a replayed call does
+struct Reply {
+    response: String,
+    delay: Duration,
+    fault: Option<MockFault>,
+}
+
+/// The scenario being replayed and how far each neuron got in it
+struct ActiveScenario {
+    name: String,
+    strict: bool,
+    neurons: HashMap<String, Vec<Step>>,
+    cursors: HashMap<String, usize>,
+}
+
+impl ActiveScenario {
+    /// The reply to `neuron_id`'s next prompt, `None` to answer it with the
+    /// layer's mock responses, or a drift error in strict mode
+    fn next(&mut self, neuron_id: &str, prompt: &str) -> Result<Option<Reply>> {
+        let steps = self.neurons.get(neuron_id).map(Vec::as_slice).unwrap_or_default();
+        let cursor = self.cursors.entry(neuron_id.to_string()).or_default();
+        let drift = match steps.get(*cursor) {
+            Some(step) => match step.reply(prompt) {
+                Some(reply) => {
+                    *cursor += 1;
+                    return Ok(Some(reply));
+                }
+                None => format!(
+                    "prompt {} of {} does not match {}",
+                    *cursor + 1,
+                    neuron_id,
+                    step.describe()
+                ),
+            },
+            None => format!("{} sent a prompt after its {} expected ones", neuron_id, steps.len()),
+        };
+
+        if !self.strict {
+            debug!("Mock scenario {}: {}; using the mock responses", self.name, drift);
+            return Ok(None);
+        }
+        error!("Mock scenario {} drifted: {}: {:?}", self.name, drift, excerpt(prompt));
+        Err(Error::ClaudeApi(format!(
+            "Mock scenario {} drifted: {}: {:?}",
+            self.name,
+            drift,
+            excerpt(prompt)
+        )))
+    }
+}
+
+/// Start of a prompt, for error messages
+fn excerpt(prompt: &str) -> String {
+    const LIMIT: usize = 120;
+    match prompt.char_indices().nth(LIMIT) {
+        Some((end, _)) => format!("{}...", &prompt[..end]),
+        None => prompt.to_string(),
+    }
+}
+
+/// Progress of one neuron through the active scenario
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NeuronProgress {
+    pub matched: usize,
+    pub expected: usize,
+}
+
+/// The scenario being replayed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ActiveScenarioStatus {
+    pub name: String,
+    pub strict: bool,
+    pub neurons: BTreeMap<String, NeuronProgress>,
+}
+
+/// Exchanges recorded so far
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordingStatus {
+    pub path: String,
+    pub exchanges: usize,
+}
+
+/// Scenarios on disk, the one replayed and the recording
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScenarioStatus {
+    /// Whether Claude runs in mock mode, the only mode scenarios replay in
+    pub replay: bool,
+    pub dir: Option<String>,
+    /// Names of the scenario files in `dir`
+    pub available: Vec<String>,
+    pub active: Option<ActiveScenarioStatus>,
+    pub recording: Option<RecordingStatus>,
+}
+
+/// Check scenario settings before the server starts
+pub fn validate(config: &ClaudeConfig) -> Result<()> {
+    let scenarios = &config.scenarios;
+    let invalid = if scenarios.active.is_some() && scenarios.dir.is_none() {
+        Some("active needs a dir to load it from".to_string())
+    } else if scenarios.active.is_some() && config.mode != "mock" {
+        Some(format!("scenarios replay in mode \"mock\" only, not \"{}\"", config.mode))
+    } else {
+        None
+    };
+    match invalid {
+        Some(reason) => Err(Error::Config(format!("Invalid claude.scenarios: {}", reason))),
+        None => Ok(()),
+    }
+}
+
+/// Scenario files of a server, the one replayed and the recorder
+pub struct MockScenarios {
+    dir: Option<PathBuf>,
+    configured: Option<String>,
+    strict: bool,
+    /// Whether neurons' calls are replayed, i.e. Claude is mocked
+    replay: bool,
+    active: RwLock<Option<ActiveScenario>>,
+    recorder: Option<Arc<ScenarioRecorder>>,
+}
+
+impl MockScenarios {
+    pub fn new(config: &ClaudeConfig) -> Self {
+        let MockScenarioConfig { dir, active, strict, record_to } = &config.scenarios;
+        Self {
+            dir: dir.as_ref().map(PathBuf::from),
+            configured: active.clone(),
+            strict: *strict,
+            replay: config.mode == "mock",
+            active: RwLock::new(None),
+            recorder: record_to.as_ref().map(|path| Arc::new(ScenarioRecorder::new(path))),
+        }
+    }
+
+    /// Load the scenario named by `HAL9_MOCK_SCENARIO` or the config
+    pub fn activate_configured(&self) -> Result<()> {
+        let name = match std::env::var(SCENARIO_ENV).ok().filter(|name| !name.is_empty()) {
+            Some(name) if !self.replay => {
+                warn!("Ignoring {}={}: scenarios replay in mock mode only", SCENARIO_ENV, name);
+                return Ok(());
+            }
+            Some(name) => name,
+            None => match &self.configured {
+                Some(name) => name.clone(),
+                None => return Ok(()),
+            },
+        };
+        self.select(Some(&name), None)
+            .map(|_| ())
+            .map_err(|e| Error::Config(format!("Mock scenario {}: {}", name, e)))
+    }
+
+    /// Names of the scenario files in the scenario directory
+    pub fn list(&self) -> Vec<String> {
+        let Some(dir) = &self.dir else {
+            return Vec::new();
+        };
+        let entries = match std::fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(e) => {
+                warn!("Cannot list mock scenarios in {}: {}", dir.display(), e);
+                return Vec::new();
+            }
+        };
+        let mut names: Vec<String> = entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| {
+                path.extension()
+                    .and_then(|ext| ext.to_str())
+                    .is_some_and(|ext| EXTENSIONS.contains(&ext))
+            })
+            .filter_map(|path| path.file_stem().and_then(|stem| stem.to_str()).map(str::to_string))
+            .collect();
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    /// Replay the scenario `name` from its start, or stop replaying with
+    /// `None`. `strict` overrides the file's and the config's setting.
+    pub fn select(&self, name: Option<&str>, strict: Option<bool>) -> ServerResult<ScenarioStatus> {
+        let Some(name) = name else {
+            if let Some(previous) = self.active.write().take() {
+                info!("Stopped replaying mock scenario {}", previous.name);
+            }
+            return Ok(self.status());
+        };
+        if !self.replay {
+            return Err(ServerError::InvalidInput(
+                "Mock scenarios replay only with claude.mode \"mock\"".to_string(),
+            ));
+        }
+        let file = self.read(name)?;
+        let strict = strict.or(file.strict).unwrap_or(self.strict);
+        let mut neurons = HashMap::new();
+        for (neuron_id, expectations) in file.neurons {
+            let steps = expectations
+                .into_iter()
+                .enumerate()
+                .map(|(i, expectation)| {
+                    Step::compile(expectation).map_err(|reason| {
+                        ServerError::InvalidInput(format!("Mock scenario {}: neurons.{}[{}]: {}", name, neuron_id, i, reason))
+                    })
+                })
+                .collect::<ServerResult<Vec<_>>>()?;
+            neurons.insert(neuron_id, steps);
+        }
+
+        info!("Replaying mock scenario {} ({} neurons, strict: {})", name, neurons.len(), strict);
+        *self.active.write() = Some(ActiveScenario {
+            name: name.to_string(),
+            strict,
+            neurons,
+            cursors: HashMap::new(),
+        });
+        Ok(self.status())
+    }
+
+    fn read(&self, name: &str) -> ServerResult<ScenarioFile> {
+        let dir = self
+            .dir
+            .as_ref()
+            .ok_or_else(|| ServerError::InvalidInput("claude.scenarios.dir is not set".to_string()))?;
+        if name.is_empty() || name.starts_with('.') || name.contains(['/', '\\']) {
+            return Err(ServerError::InvalidInput(format!("{:?} is not a scenario name", name)));
+        }
+        let path = EXTENSIONS
+            .iter()
+            .map(|ext| dir.join(format!("{}.{}", name, ext)))
+            .find(|path| path.is_file())
+            .ok_or_else(|| ServerError::NotFound(format!("Mock scenario {} not found in {}", name, dir.display())))?;
+        let text = std::fs::read_to_string(&path)?;
+        let parsed = if path.extension().is_some_and(|ext| ext == "json") {
+            serde_json::from_str(&text).map_err(|e| e.to_string())
+        } else {
+            serde_yaml::from_str(&text).map_err(|e| e.to_string())
+        };
+        parsed.map_err(|e| ServerError::InvalidInput(format!("Mock scenario {} is invalid: {}", path.display(), e)))
+    }
+
+    pub fn status(&self) -> ScenarioStatus {
+        let active = self.active.read().as_ref().map(|active| ActiveScenarioStatus {
+            name: active.name.clone(),
+            strict: active.strict,
+            neurons: active
+                .neurons
+                .iter()
+                .map(|(neuron_id, steps)| {
+                    let matched = active.cursors.get(neuron_id).copied().unwrap_or_default();
+                    (neuron_id.clone(), NeuronProgress { matched, expected: steps.len() })
+                })
+                .collect(),
+        });
+        ScenarioStatus {
+            replay: self.replay,
+            dir: self.dir.as_ref().map(|dir| dir.display().to_string()),
+            available: self.list(),
+            active,
+            recording: self.recorder.as_ref().map(|recorder| recorder.status()),
+        }
+    }
+
+    /// The recorder, when `claude.scenarios.record_to` is set
+    pub fn recorder(&self) -> Option<Arc<ScenarioRecorder>> {
+        self.recorder.clone()
+    }
+
+    /// Replay the active scenario to a neuron's Claude instance in mock mode
+    /// and record its exchanges when recording
+    pub fn wrap_claude(self: &Arc<Self>, claude: Box<dyn ClaudeInterface>, neuron_id: &str) -> Box<dyn ClaudeInterface> {
+        let claude: Box<dyn ClaudeInterface> = if self.replay {
+            Box::new(ScenarioClaude { inner: claude, neuron_id: neuron_id.to_string(), scenarios: self.clone() })
+        } else {
+            claude
+        };
+        match &self.recorder {
+            Some(recorder) => recorder.wrap_claude(claude, neuron_id),
+            None => claude,
+        }
+    }
+
+    fn next(&self, neuron_id: &str, prompt: &str) -> Result<Option<Reply>> {
+        match self.active.write().as_mut() {
+            Some(active) => active.next(neuron_id, prompt),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Claude instance answering from the active scenario
+pub struct ScenarioClaude {
+    inner: Box<dyn ClaudeInterface>,
+    neuron_id: String,
+    scenarios: Arc<MockScenarios>,
+}
+
+#[async_trait]
+impl ClaudeInterface for ScenarioClaude {
+    async fn send_message(&self, message: &str) -> Result<String> {
+        let Some(reply) = self.scenarios.next(&self.neuron_id, message)? else {
+            return self.inner.send_message(message).await;
+        };
+        tokio::time::sleep(reply.delay).await;
+        match reply.fault {
+            Some(MockFault::Error { message }) => Err(Error::ClaudeApi(message)),
+            Some(MockFault::Timeout) => Err(Error::Timeout(reply.delay.as_secs())),
+            None => Ok(reply.response),
+        }
+    }
+
+    /// Scenario replies stream a line at a time, their delay spread across
+    /// the lines
+    async fn stream_message(&self, message: &str, partial: &mpsc::UnboundedSender<String>) -> Result<String> {
+        let Some(reply) = self.scenarios.next(&self.neuron_id, message)? else {
+            return self.inner.stream_message(message, partial).await;
+        };
+        if reply.fault.is_some() {
+            tokio::time::sleep(reply.delay).await;
+        }
+        match reply.fault {
+            Some(MockFault::Error { message }) => Err(Error::ClaudeApi(message)),
+            Some(MockFault::Timeout) => Err(Error::Timeout(reply.delay.as_secs())),
+            None => {
+                let lines: Vec<&str> = reply.response.split_inclusive('\n').collect();
+                let per_line = reply.delay / lines.len().max(1) as u32;
+                for line in lines {
+                    tokio::time::sleep(per_line).await;
+                    let _ = partial.send(line.to_string());
+                }
+                Ok(reply.response)
+            }
+        }
+    }
+
+    fn system_prompt(&self) -> &str {
+        self.inner.system_prompt()
+    }
+
+    fn last_token_usage(&self) -> Option<TokenUsage> {
+        self.inner.last_token_usage()
+    }
+}
+
+/// Where a recording was written
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordingSaved {
+    pub path: String,
+    pub neurons: usize,
+    pub exchanges: usize,
+}
+
+/// Exchanges of every neuron, written out as a scenario file
+pub struct ScenarioRecorder {
+    path: PathBuf,
+    /// Exchanges of each neuron in the order their prompts were sent;
+    /// `None` while the response is outstanding
+    exchanges: Mutex<BTreeMap<String, Vec<Option<Expectation>>>>,
+}
+
+impl ScenarioRecorder {
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Self { path: path.as_ref().to_path_buf(), exchanges: Mutex::new(BTreeMap::new()) }
+    }
+
+    /// Record the exchanges of a neuron's Claude instance
+    pub fn wrap_claude(self: &Arc<Self>, claude: Box<dyn ClaudeInterface>, neuron_id: &str) -> Box<dyn ClaudeInterface> {
+        Box::new(RecordingClaude { inner: claude, neuron_id: neuron_id.to_string(), recorder: self.clone() })
+    }
+
+    /// Reserve the next exchange of `neuron_id`
+    fn begin(&self, neuron_id: &str) -> usize {
+        let mut exchanges = self.exchanges.lock();
+        let neuron = exchanges.entry(neuron_id.to_string()).or_default();
+        neuron.push(None);
+        neuron.len() - 1
+    }
+
+    fn finish(&self, neuron_id: &str, index: usize, prompt: &str, outcome: &Result<String>) {
+        let (response, fault) = match outcome {
+            // Responses are templates; keep their `$` literal
+            Ok(response) => (response.replace('$', "$$"), None),
+            Err(Error::Timeout(_)) => (String::new(), Some(MockFault::Timeout)),
+            Err(Error::ClaudeApi(message)) => (String::new(), Some(MockFault::Error { message: message.clone() })),
+            Err(other) => (String::new(), Some(MockFault::Error { message: other.to_string() })),
+        };
+        let expectation = Expectation {
+            matcher: PromptMatch { regex: Some(prompt_pattern(prompt)), json_path: None },
+            response,
+            delay_ms: 0,
+            fault,
+        };
+        if let Some(slot) = self.exchanges.lock().get_mut(neuron_id).and_then(|neuron| neuron.get_mut(index)) {
+            *slot = Some(expectation);
+        }
+    }
+
+    /// The exchanges recorded so far as a scenario; outstanding ones are left out
+    pub fn scenario(&self) -> ScenarioFile {
+        let neurons = self
+            .exchanges
+            .lock()
+            .iter()
+            .map(|(neuron_id, exchanges)| (neuron_id.clone(), exchanges.iter().flatten().cloned().collect::<Vec<_>>()))
+            .filter(|(_, exchanges)| !exchanges.is_empty())
+            .collect();
+        ScenarioFile {
+            description: Some(format!("Recorded {}", chrono::Utc::now().to_rfc3339())),
+            strict: Some(true),
+            neurons,
+        }
+    }
+
+    /// Write the recording to its path, as JSON for a `.json` path and YAML
+    /// otherwise
+    pub fn save(&self) -> ServerResult<RecordingSaved> {
+        let scenario = self.scenario();
+        let text = if self.path.extension().is_some_and(|ext| ext == "json") {
+            serde_json::to_string_pretty(&scenario)?
+        } else {
+            serde_yaml::to_string(&scenario).map_err(|e| ServerError::Internal(e.to_string()))?
+        };
+        if let Some(parent) = self.path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&self.path, text)?;
+
+        let saved = RecordingSaved {
+            path: self.path.display().to_string(),
+            neurons: scenario.neurons.len(),
+            exchanges: scenario.neurons.values().map(Vec::len).sum(),
+        };
+        info!("Recorded {} exchanges of {} neurons to {}", saved.exchanges, saved.neurons, saved.path);
+        Ok(saved)
+    }
+
+    pub fn status(&self) -> RecordingStatus {
+        RecordingStatus {
+            path: self.path.display().to_string(),
+            exchanges: self.exchanges.lock().values().map(|neuron| neuron.iter().flatten().count()).sum(),
+        }
+    }
+}
+
+/// Regex matching `prompt` exactly, with any UUID or timestamp in their place
+pub fn prompt_pattern(prompt: &str) -> String {
+    static VOLATILE: OnceLock<Regex> = OnceLock::new();
+    let volatile = VOLATILE.get_or_init(|| {
+        Regex::new(concat!(
+            r"(?P<uuid>\b[0-9a-fA-F]{8}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{12}\b)",
+            r"|\d{4}-\d{2}-\d{2}[T ]\d{2}:\d{2}:\d{2}(?:\.\d+)?(?:Z|[+-]\d{2}:\d{2})?",
+        ))
+        .expect("volatile pattern")
+    });
+
+    let mut pattern = String::from(r"(?s)\A");
+    let mut last = 0;
+    for found in volatile.captures_iter(prompt) {
+        let whole = found.get(0).expect("whole match");
+        pattern.push_str(&regex::escape(&prompt[last..whole.start()]));
+        pattern.push_str(if found.name("uuid").is_some() {
+            r"[0-9a-fA-F-]{36}"
+        } else {
+            r"\d{4}-\d{2}-\d{2}[T ][0-9:.]+(?:Z|[+-]\d{2}:\d{2})?"
+        });
+        last = whole.end();
+    }
+    pattern.push_str(&regex::escape(&prompt[last..]));
+    pattern.push_str(r"\z");
+    pattern
+}
+
+/// Claude instance recording its exchanges
+pub struct RecordingClaude {
+    inner: Box<dyn ClaudeInterface>,
+    neuron_id: String,
+    recorder: Arc<ScenarioRecorder>,
+}
+
+#[async_trait]
+impl ClaudeInterface for RecordingClaude {
+    async fn send_message(&self, message: &str) -> Result<String> {
+        let index = self.recorder.begin(&self.neuron_id);
+        let outcome = self.inner.send_message(message).await;
+        self.recorder.finish(&self.neuron_id, index, message, &outcome);
+        outcome
+    }
+
+    async fn stream_message(&self, message: &str, partial: &mpsc::UnboundedSender<String>) -> Result<String> {
+        let index = self.recorder.begin(&self.neuron_id);
+        let outcome = self.inner.stream_message(message, partial).await;
+        self.recorder.finish(&self.neuron_id, index, message, &outcome);
+        outcome
+    }
+
+    fn system_prompt(&self) -> &str {
+        self.inner.system_prompt()
+    }
+
+    fn last_token_usage(&self) -> Option<TokenUsage> {
+        self.inner.last_token_usage()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_path_selects_from_the_first_object_in_the_prompt() {
+        let prompt = "Content: {\"task\": {\"steps\": [\"parse\", 2]}}\nFeatures: {}";
+        let path = parse_json_path("$.task.steps[0]").unwrap();
+        assert_eq!(select_json(prompt, &path).as_deref(), Some("parse"));
+        let path = parse_json_path("$.task.steps[1]").unwrap();
+        assert_eq!(select_json(prompt, &path).as_deref(), Some("2"));
+        assert_eq!(select_json(prompt, &parse_json_path("$.missing").unwrap()), None);
+        assert!(parse_json_path("task.steps").is_err());
+        assert!(parse_json_path("$.task[x]").is_err());
+    }
+
+    #[test]
+    fn test_recorded_pattern_ignores_uuids_and_timestamps() {
+        let pattern = Regex::new(&prompt_pattern(
+            "Chain 6f1c2a9e-1b2c-4d5e-8f90-0123456789ab at 2026-01-02T03:04:05.678Z costs $1 (really?)",
+        ))
+        .unwrap();
+        assert!(pattern.is_match(
+            "Chain 0a1b2c3d-4e5f-4a6b-8c7d-8e9f0a1b2c3d at 2027-11-12T13:14:15+09:00 costs $1 (really?)"
+        ));
+        assert!(!pattern.is_match("Chain x at 2026-01-02T03:04:05Z costs $1 (really?)"));
+        assert!(!pattern.is_match(
+            "Chain 6f1c2a9e-1b2c-4d5e-8f90-0123456789ab at 2026-01-02T03:04:05.678Z costs $2 (really?)"
+        ));
+    }
+}
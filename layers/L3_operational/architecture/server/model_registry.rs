@@ -0,0 +1,291 @@
+//! Lifecycle of provider models
+//!
+//! Configs name models by their exact identifier, and a retired one makes
+//! every call fail with a 404 from the provider. The registry knows each
+//! model's status: active, deprecated until a sunset date, or retired. A
+//! deprecated model is treated as retired from its sunset date on.
+//!
+//! The models a config names are checked at startup and every
+//! `models.check_interval_secs`, with a warning for each deprecated one and
+//! an error for each retired one without a replacement. At call time a
+//! retired model is replaced by its entry in `models.remap`, following
+//! further remaps if the replacement is retired too. Calls to a retired
+//! model without one fail with [`Error::ModelRetired`] instead of reaching
+//! the provider. A substitution is written to the signals the response
+//! produces under `model.*`, and the call is priced as the model that
+//! served it.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::{error, info, warn};
+
+use hal9_core::config::{ModelLifecycle, ModelsConfig, RecoveryStepConfig};
+use hal9_core::{metadata_schema::keys, Error, Result, ServerConfig};
+
+use crate::claude::model_pricing;
+use crate::local_model::LOCAL_MODEL_PREFIX;
+
+/// Metadata key of the model a neuron is configured with, when a retired
+/// one was replaced
+pub const MODEL_REQUESTED_KEY: &str = keys::model::REQUESTED;
+
+/// Metadata key of the model that served the call in its place
+pub const MODEL_REMAPPED_TO_KEY: &str = keys::model::REMAPPED_TO;
+
+/// Sunset as (year, month, day)
+type Sunset = Option<(i32, u32, u32)>;
+
+/// Models known without configuration: (id, status, sunset)
+const BUILT_IN: &[(&str, ModelLifecycle, Sunset)] = &[
+    ("claude-3-opus-20240229", ModelLifecycle::Deprecated, Some((2026, 1, 5))),
+    ("claude-3-sonnet-20240229", ModelLifecycle::Retired, None),
+    ("claude-3-haiku-20240307", ModelLifecycle::Active, None),
+    ("claude-3-5-sonnet-20240620", ModelLifecycle::Deprecated, Some((2025, 10, 22))),
+    ("claude-3-5-sonnet-20241022", ModelLifecycle::Deprecated, Some((2025, 10, 22))),
+    ("claude-3-5-sonnet-latest", ModelLifecycle::Active, None),
+    ("claude-3-5-haiku-20241022", ModelLifecycle::Active, None),
+];
+
+/// A model's status on a given day
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum ModelStatus {
+    Active,
+    Deprecated { sunset: NaiveDate, days_to_sunset: i64 },
+    Retired,
+    /// Not in the registry; called as configured
+    Unknown,
+}
+
+/// The model a call goes to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModelResolution {
+    /// Model to call
+    pub model: String,
+    /// Configured model, when it was retired and replaced
+    pub remapped_from: Option<String>,
+}
+
+impl ModelResolution {
+    /// Write the substitution, if any, into signal metadata
+    pub fn apply_to(&self, metadata: &mut HashMap<String, String>) {
+        if let Some(requested) = &self.remapped_from {
+            metadata.insert(MODEL_REQUESTED_KEY.to_string(), requested.clone());
+            metadata.insert(MODEL_REMAPPED_TO_KEY.to_string(), self.model.clone());
+        }
+    }
+}
+
+/// Outcome of checking one configured model
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModelCheck {
+    pub model: String,
+    #[serde(flatten)]
+    pub status: ModelStatus,
+    /// Model calls go to instead, for a retired model
+    pub replacement: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Entry {
+    status: ModelLifecycle,
+    sunset: Option<NaiveDate>,
+}
+
+/// Known models and the replacements of retired ones
+pub struct ModelRegistry {
+    entries: HashMap<String, Entry>,
+    remap: BTreeMap<String, String>,
+}
+
+impl Default for ModelRegistry {
+    fn default() -> Self {
+        Self::new(&ModelsConfig::default())
+    }
+}
+
+impl ModelRegistry {
+    /// The built-in registry with the configured entries and remaps
+    pub fn new(config: &ModelsConfig) -> Self {
+        let mut entries: HashMap<String, Entry> = BUILT_IN
+            .iter()
+            .map(|(id, status, sunset)| {
+                let sunset = sunset.and_then(|(y, m, d)| NaiveDate::from_ymd_opt(y, m, d));
+                (id.to_string(), Entry { status: *status, sunset })
+            })
+            .collect();
+        for entry in &config.registry {
+            entries.insert(entry.id.clone(), Entry { status: entry.status, sunset: entry.sunset });
+        }
+        Self {
+            entries,
+            remap: config.remap.iter().map(|(from, to)| (from.clone(), to.clone())).collect(),
+        }
+    }
+
+    /// Status of `model` on `today`; a deprecated model is retired from
+    /// its sunset date on
+    pub fn status(&self, model: &str, today: NaiveDate) -> ModelStatus {
+        let Some(entry) = self.entries.get(model) else {
+            return ModelStatus::Unknown;
+        };
+        match (entry.status, entry.sunset) {
+            (ModelLifecycle::Active, _) => ModelStatus::Active,
+            (ModelLifecycle::Retired, _) => ModelStatus::Retired,
+            (ModelLifecycle::Deprecated, Some(sunset)) if sunset <= today => ModelStatus::Retired,
+            (ModelLifecycle::Deprecated, Some(sunset)) => ModelStatus::Deprecated {
+                sunset,
+                days_to_sunset: (sunset - today).num_days(),
+            },
+            // Rejected by `validate`; without a date it is still served
+            (ModelLifecycle::Deprecated, None) => ModelStatus::Active,
+        }
+    }
+
+    /// Model a call configured with `model` goes to on `today`
+    pub fn resolve_on(&self, model: &str, today: NaiveDate) -> Result<ModelResolution> {
+        let mut current = model;
+        let mut seen = HashSet::new();
+        while self.status(current, today) == ModelStatus::Retired {
+            if !seen.insert(current) {
+                break;
+            }
+            match self.remap.get(current) {
+                Some(replacement) => current = replacement,
+                None => return Err(Error::ModelRetired { model: current.to_string() }),
+            }
+        }
+        if self.status(current, today) == ModelStatus::Retired {
+            return Err(Error::ModelRetired { model: current.to_string() });
+        }
+        Ok(ModelResolution {
+            model: current.to_string(),
+            remapped_from: (current != model).then(|| model.to_string()),
+        })
+    }
+
+    /// Model a call configured with `model` goes to now
+    pub fn resolve(&self, model: &str) -> Result<ModelResolution> {
+        self.resolve_on(model, Utc::now().date_naive())
+    }
+
+    /// Model that calls configured with `model` are billed as: its
+    /// replacement when retired, otherwise the model itself
+    pub fn billed_model(&self, model: &str) -> String {
+        self.resolve(model).map_or_else(|_| model.to_string(), |resolution| resolution.model)
+    }
+
+    /// Prompt and completion cost per 1k tokens of calls configured with
+    /// `model`, following remapping
+    pub fn pricing(&self, model: &str) -> (f64, f64) {
+        model_pricing(&self.billed_model(model))
+    }
+
+    /// Status of each of `models` on `today`
+    pub fn check(&self, models: &[String], today: NaiveDate) -> Vec<ModelCheck> {
+        models
+            .iter()
+            .map(|model| {
+                let status = self.status(model, today);
+                let replacement = match status {
+                    ModelStatus::Retired => self.resolve_on(model, today).ok().map(|resolution| resolution.model),
+                    _ => None,
+                };
+                ModelCheck { model: model.clone(), status, replacement }
+            })
+            .collect()
+    }
+}
+
+/// Log a warning for each deprecated model and an error for each retired
+/// one that calls cannot be remapped from
+pub fn log_checks(checks: &[ModelCheck]) {
+    for check in checks {
+        match (&check.status, &check.replacement) {
+            (ModelStatus::Deprecated { sunset, days_to_sunset }, _) => warn!(
+                target: "models",
+                model = %check.model,
+                %sunset,
+                days_to_sunset,
+                "Model {} is deprecated and retires in {} days, on {}", check.model, days_to_sunset, sunset
+            ),
+            (ModelStatus::Retired, Some(replacement)) => info!(
+                target: "models",
+                model = %check.model,
+                replacement = %replacement,
+                "Model {} is retired, calls go to {}", check.model, replacement
+            ),
+            (ModelStatus::Retired, None) => error!(
+                target: "models",
+                model = %check.model,
+                "Model {} is retired and has no entry in models.remap; calls to it will fail", check.model
+            ),
+            _ => {}
+        }
+    }
+}
+
+/// Provider models a config names, without local models
+pub fn configured_models(config: &ServerConfig) -> Vec<String> {
+    let mut models = vec![config.claude.model.clone(), config.output_format.reformat_model.clone()];
+    if let Some(period) = &config.claude.cost_controls.budget_period {
+        models.extend(period.fallback_model.clone());
+    }
+    for playbook in config.recovery.values() {
+        for step in &playbook.steps {
+            match step {
+                RecoveryStepConfig::FallbackModel { model } | RecoveryStepConfig::SummarizeAndDegrade { model, .. } => {
+                    models.push(model.clone());
+                }
+                _ => {}
+            }
+        }
+    }
+    models.retain(|model| !model.starts_with(LOCAL_MODEL_PREFIX));
+    models.sort();
+    models.dedup();
+    models
+}
+
+/// Check the configured models now and every `interval`
+pub async fn check_task(registry: Arc<ModelRegistry>, models: Vec<String>, interval: Duration) {
+    let mut ticker = tokio::time::interval(interval.max(Duration::from_secs(1)));
+    loop {
+        ticker.tick().await;
+        log_checks(&registry.check(&models, Utc::now().date_naive()));
+    }
+}
+
+/// Check that deprecated entries have a sunset date and that no remap
+/// points back at itself
+pub fn validate(config: &ModelsConfig) -> Result<()> {
+    let undated = config
+        .registry
+        .iter()
+        .find(|entry| entry.status == ModelLifecycle::Deprecated && entry.sunset.is_none());
+    let invalid = if let Some(entry) = undated {
+        Some(format!("deprecated model {} needs a sunset date", entry.id))
+    } else if config.check_interval_secs == 0 {
+        Some("check_interval_secs must be positive".to_string())
+    } else {
+        config.remap.keys().find_map(|from| {
+            let mut current = from;
+            let mut seen = HashSet::new();
+            while let Some(next) = config.remap.get(current) {
+                if !seen.insert(current) {
+                    return Some(format!("remap of {} loops", from));
+                }
+                current = next;
+            }
+            None
+        })
+    };
+    match invalid {
+        Some(reason) => Err(Error::Config(format!("Invalid models: {}", reason))),
+        None => Ok(()),
+    }
+}
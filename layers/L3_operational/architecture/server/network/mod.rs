@@ -4,8 +4,10 @@ pub mod tcp_transport;
 pub mod discovery;
 pub mod protocol;
 pub mod connection_pool;
+pub mod tls;
 
 pub use tcp_transport::{TcpTransport, TransportConfig};
 pub use discovery::{ServiceDiscovery, DiscoveryConfig, ServerInfo};
 pub use protocol::{NetworkMessage, MessageCodec};
-pub use connection_pool::{ConnectionPool, ConnectionManager};
\ No newline at end of file
+pub use connection_pool::{ConnectionPool, ConnectionManager};
+pub use tls::{TlsManager, FailureKind, PEER_IDENTITY_KEY};
\ No newline at end of file
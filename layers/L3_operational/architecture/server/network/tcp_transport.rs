@@ -1,19 +1,40 @@
 //! TCP transport layer for neuron communication
+//!
+//! Peers agree on compression, batching and the signal schema version in
+//! the hello exchange: each announces what it accepts, and a feature is only
+//! used on a connection when the peer announced it, so servers without them
+//! interoperate. Signals are written to a peer in the highest schema version
+//! both read; peers with no version in common are refused.
+//! Batching coalesces signals for the same peer for up to the configured
+//! window; signals marked high priority and control signals flush the
+//! batch and go out at once.
 
+use std::collections::HashMap;
 use std::net::SocketAddr;
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::net::{TcpListener, TcpStream};
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
-use tokio::sync::{mpsc, RwLock};
+use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt, ReadHalf, WriteHalf};
+use tokio::sync::{mpsc, Mutex, RwLock};
 use tokio::time::timeout;
 use tracing::{debug, error, info, warn};
 use dashmap::DashMap;
 
-use hal9_core::{Result, Error, NeuronSignal};
-use crate::network::protocol::{NetworkMessage, MessageCodec};
+use hal9_core::{metadata_schema::keys, signal_schema, Result, Error, NeuronSignal};
+use crate::network::protocol::{
+    peer_signal_schemas, signal_schema_capability, NetworkMessage, MessageCodec, Compression, CAPABILITY_BATCH,
+    CAPABILITY_ZSTD,
+};
+use crate::network::tls::{peer_identity, server_name, ConnectError, FailureKind, TlsManager, PEER_IDENTITY_KEY};
+use crate::chaos::ChaosEngine;
 use crate::metrics::Metrics;
 
+/// Error code sent to a peer refused during the handshake
+const UNAUTHORIZED_CODE: &str = "UNAUTHORIZED";
+
+/// Value of the `network.priority` metadata key for signals that skip batching
+pub const HIGH_PRIORITY: &str = "high";
+
 /// Configuration for TCP transport
 #[derive(Debug, Clone)]
 pub struct TransportConfig {
@@ -35,6 +56,20 @@ pub struct TransportConfig {
     pub tls_cert_path: Option<String>,
     /// TLS key path
     pub tls_key_path: Option<String>,
+    /// CA bundle that peer certificates must chain to
+    pub tls_ca_path: Option<String>,
+    /// Require inbound peers to present a client certificate
+    pub tls_require_client_cert: bool,
+    /// Layers each peer (by certificate identity) may send signals to.
+    /// When non-empty, peers not listed are refused.
+    pub peer_allowed_layers: HashMap<String, Vec<String>>,
+    /// zstd compression offered to peers; `None` sends everything plain
+    pub compression: Option<Compression>,
+    /// Coalesce signals for the same peer for up to this long; zero sends
+    /// every signal immediately
+    pub batch_window: Duration,
+    /// Send a batch as soon as it holds this many signals
+    pub batch_max_signals: usize,
 }
 
 impl Default for TransportConfig {
@@ -49,27 +84,154 @@ impl Default for TransportConfig {
             tls_enabled: false,
             tls_cert_path: None,
             tls_key_path: None,
+            tls_ca_path: None,
+            tls_require_client_cert: false,
+            peer_allowed_layers: HashMap::new(),
+            compression: Some(Compression { level: 3, threshold: 1024 }),
+            batch_window: Duration::ZERO,
+            batch_max_signals: 64,
         }
     }
 }
 
+/// Plain TCP or TLS stream to a peer
+trait PeerStream: AsyncRead + AsyncWrite + Unpin + Send {}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> PeerStream for T {}
+
+type BoxedStream = Box<dyn PeerStream>;
+
 /// TCP transport for distributed neuron communication
 pub struct TcpTransport {
     config: TransportConfig,
     server_id: String,
     listener: Option<TcpListener>,
+    local_addr: Option<SocketAddr>,
     connections: Arc<DashMap<String, Arc<Connection>>>,
     signal_tx: mpsc::Sender<(String, NeuronSignal)>,
     signal_rx: RwLock<Option<mpsc::Receiver<(String, NeuronSignal)>>>,
     shutdown_tx: Option<mpsc::Sender<()>>,
     metrics: Option<Arc<Metrics>>,
+    tls: Option<Arc<TlsManager>>,
+    /// Drops signals to peers while a chaos injection says so
+    chaos: Option<Arc<ChaosEngine>>,
 }
 
 /// Active connection to a remote server
 struct Connection {
     peer_id: String,
-    stream: Arc<RwLock<TcpStream>>,
+    /// Certificate identity of the peer when the link is TLS
+    identity: Option<String>,
+    writer: Mutex<WriteHalf<BoxedStream>>,
     last_activity: RwLock<std::time::Instant>,
+    /// Compression negotiated with the peer
+    compression: Option<Compression>,
+    /// Signal schema version signals are written to the peer in
+    signal_schema: u16,
+    /// Signals waiting for the next batch; `None` when batching is off or
+    /// the peer does not accept batches
+    outbox: Option<Mutex<Vec<NeuronSignal>>>,
+}
+
+impl Connection {
+    /// Encode and write one message, compressed when negotiated
+    async fn write_message(&self, msg: &NetworkMessage, io_timeout: Duration, metrics: Option<&Metrics>) -> Result<()> {
+        let frame = MessageCodec::encode_with(msg, self.compression.as_ref())?;
+        let mut writer = self.writer.lock().await;
+        
+        timeout(io_timeout, async {
+            writer.write_all(&frame.bytes).await?;
+            writer.flush().await
+        }).await
+            .map_err(|_| Error::Network("Send timeout".to_string()))?
+            .map_err(|e| Error::Network(format!("Send error: {}", e)))?;
+            
+        drop(writer);
+        
+        // Update last activity
+        *self.last_activity.write().await = std::time::Instant::now();
+        
+        if let Some(metrics) = metrics {
+            metrics.record_transport_sent(frame.uncompressed_len, frame.wire_len(), frame.compressed);
+        }
+        Ok(())
+    }
+    
+    /// Send the queued signals, as one batch message when there are several.
+    /// Callers hold the outbox lock throughout, which keeps batches in order.
+    async fn write_queued(&self, queued: &mut Vec<NeuronSignal>, io_timeout: Duration, metrics: Option<&Metrics>) -> Result<()> {
+        match queued.len() {
+            0 => Ok(()),
+            1 => {
+                let msg = NetworkMessage::Signal(Box::new(queued.remove(0)));
+                self.write_message(&msg, io_timeout, metrics).await
+            }
+            count => {
+                let msg = NetworkMessage::Batch { signals: std::mem::take(queued) };
+                self.write_message(&msg, io_timeout, metrics).await?;
+                if let Some(metrics) = metrics {
+                    metrics.record_transport_batch(count);
+                }
+                Ok(())
+            }
+        }
+    }
+    
+    /// `signal` in the schema version negotiated with the peer
+    fn for_peer(&self, signal: NeuronSignal) -> Result<NeuronSignal> {
+        if self.signal_schema == signal_schema::CURRENT_SCHEMA_VERSION {
+            Ok(signal)
+        } else {
+            signal_schema::downgrade(&signal, self.signal_schema)
+        }
+    }
+    
+    /// Send whatever the outbox holds
+    async fn flush(&self, io_timeout: Duration, metrics: Option<&Metrics>) -> Result<()> {
+        match &self.outbox {
+            Some(outbox) => self.write_queued(&mut *outbox.lock().await, io_timeout, metrics).await,
+            None => Ok(()),
+        }
+    }
+}
+
+/// What a peer announced in its hello
+struct PeerHello {
+    server_id: String,
+    capabilities: Vec<String>,
+    /// Highest signal schema version both sides read
+    signal_schema: u16,
+}
+
+impl PeerHello {
+    fn offers(&self, capability: &str) -> bool {
+        self.capabilities.iter().any(|c| c == capability)
+    }
+}
+
+/// Signals that never wait for a batch: marked high priority, or control
+fn is_urgent(signal: &NeuronSignal) -> bool {
+    signal.metadata.get(keys::network::PRIORITY).is_some_and(|p| p == HIGH_PRIORITY)
+        || signal.metadata.get(keys::routing::IS_CONTROL).is_some_and(|c| c == "true")
+}
+
+/// State shared by every connection task
+#[derive(Clone)]
+struct ConnectionContext {
+    connections: Arc<DashMap<String, Arc<Connection>>>,
+    signal_tx: mpsc::Sender<(String, NeuronSignal)>,
+    config: TransportConfig,
+    metrics: Option<Arc<Metrics>>,
+    server_id: String,
+    tls: Option<Arc<TlsManager>>,
+}
+
+impl ConnectionContext {
+    fn record_failure(&self, err: &ConnectError) {
+        if let Some(metrics) = &self.metrics {
+            metrics.record_peer_connection_failure(err.kind.as_str());
+        }
+    }
 }
 
 impl TcpTransport {
@@ -81,11 +243,14 @@ impl TcpTransport {
             config,
             server_id,
             listener: None,
+            local_addr: None,
             connections: Arc::new(DashMap::new()),
             signal_tx,
             signal_rx: RwLock::new(Some(signal_rx)),
             shutdown_tx: None,
             metrics: None,
+            tls: None,
+            chaos: None,
         }
     }
     
@@ -94,12 +259,23 @@ impl TcpTransport {
         self.metrics = Some(metrics);
     }
     
-    /// Start the transport layer
+    /// Set the engine whose injections may drop outgoing signals
+    pub fn set_chaos(&mut self, chaos: Arc<ChaosEngine>) {
+        self.chaos = Some(chaos);
+    }
+    
+    /// Start the transport layer. Loads TLS certificates when enabled.
     pub async fn start(&mut self) -> Result<()> {
+        if self.config.tls_enabled && self.tls.is_none() {
+            self.tls = Some(Arc::new(TlsManager::load(&self.config)?));
+            info!("TLS enabled for network transport (mutual: {})", self.config.tls_require_client_cert);
+        }
+        
         // Bind to the configured address
         let listener = TcpListener::bind(&self.config.bind_address).await
             .map_err(|e| Error::Network(format!("Failed to bind to {}: {}", self.config.bind_address, e)))?;
             
+        self.local_addr = listener.local_addr().ok();
         info!("TCP transport listening on {}", self.config.bind_address);
         self.listener = Some(listener);
         
@@ -112,16 +288,37 @@ impl TcpTransport {
         Ok(())
     }
     
+    /// Address the transport is listening on, once started
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        self.local_addr
+    }
+    
+    /// Re-read TLS certificate, key and CA files. New connections use the
+    /// reloaded certificates; established ones are unaffected.
+    pub fn reload_tls(&self) -> Result<()> {
+        match &self.tls {
+            Some(tls) => tls.reload(),
+            None => Err(Error::InvalidState("TLS is not enabled for the network transport".to_string())),
+        }
+    }
+    
+    fn context(&self) -> ConnectionContext {
+        ConnectionContext {
+            connections: self.connections.clone(),
+            signal_tx: self.signal_tx.clone(),
+            config: self.config.clone(),
+            metrics: self.metrics.clone(),
+            server_id: self.server_id.clone(),
+            tls: self.tls.clone(),
+        }
+    }
+    
     /// Start accepting incoming connections
     async fn start_accept_loop(&mut self) -> Result<()> {
         let listener = self.listener.take()
             .ok_or_else(|| Error::InvalidState("Listener not initialized".to_string()))?;
             
-        let connections = self.connections.clone();
-        let signal_tx = self.signal_tx.clone();
-        let config = self.config.clone();
-        let metrics = self.metrics.clone();
-        let server_id = self.server_id.clone();
+        let ctx = self.context();
         
         let (shutdown_tx, mut shutdown_rx) = mpsc::channel(1);
         self.shutdown_tx = Some(shutdown_tx);
@@ -135,29 +332,16 @@ impl TcpTransport {
                                 debug!("Accepted connection from {}", peer_addr);
                                 
                                 // Check connection limit
-                                if connections.len() >= config.max_connections {
+                                if ctx.connections.len() >= ctx.config.max_connections {
                                     warn!("Connection limit reached, rejecting {}", peer_addr);
                                     let _ = stream.shutdown().await;
                                     continue;
                                 }
                                 
                                 // Handle connection
-                                let connections = connections.clone();
-                                let signal_tx = signal_tx.clone();
-                                let config = config.clone();
-                                let metrics = metrics.clone();
-                                let server_id = server_id.clone();
-                                
+                                let ctx = ctx.clone();
                                 tokio::spawn(async move {
-                                    if let Err(e) = Self::handle_connection(
-                                        stream,
-                                        peer_addr,
-                                        connections,
-                                        signal_tx,
-                                        config,
-                                        metrics,
-                                        server_id
-                                    ).await {
+                                    if let Err(e) = Self::handle_connection(stream, peer_addr, ctx).await {
                                         error!("Connection handler error: {}", e);
                                     }
                                 });
@@ -181,119 +365,203 @@ impl TcpTransport {
     
     /// Handle an incoming connection
     async fn handle_connection(
-        mut stream: TcpStream,
-        _peer_addr: SocketAddr,
-        connections: Arc<DashMap<String, Arc<Connection>>>,
-        signal_tx: mpsc::Sender<(String, NeuronSignal)>,
-        config: TransportConfig,
-        metrics: Option<Arc<Metrics>>,
-        server_id: String,
+        stream: TcpStream,
+        peer_addr: SocketAddr,
+        ctx: ConnectionContext,
     ) -> Result<()> {
-        // Set TCP options
-        stream.set_nodelay(true)
-            .map_err(|e| Error::Network(format!("Failed to set TCP nodelay: {}", e)))?;
-        
-        // Perform handshake
-        let peer_id = Self::perform_handshake(&mut stream, &config, &server_id).await?;
-        info!("Handshake completed with peer {}", peer_id);
-        
-        // Create connection
-        let connection = Arc::new(Connection {
-            peer_id: peer_id.clone(),
-            stream: Arc::new(RwLock::new(stream)),
-            last_activity: RwLock::new(std::time::Instant::now()),
-        });
+        let (hello, identity, stream) = match Self::accept_peer(stream, &ctx).await {
+            Ok(accepted) => accepted,
+            Err(e) => {
+                ctx.record_failure(&e);
+                warn!("Refused connection from {}: {}", peer_addr, e);
+                return Err(e.into());
+            }
+        };
+        info!("Handshake completed with peer {}", hello.server_id);
         
-        // Store connection
-        connections.insert(peer_id.clone(), connection.clone());
+        let (connection, reader) = Self::register(hello, identity, stream, &ctx);
         
         // Start read loop
-        Self::connection_read_loop(
-            connection,
-            connections,
-            signal_tx,
-            config,
-            metrics
-        ).await
+        Self::connection_read_loop(connection, reader, ctx).await
     }
     
-    /// Perform handshake with remote peer
-    async fn perform_handshake(stream: &mut TcpStream, config: &TransportConfig, server_id: &str) -> Result<String> {
-        // Send hello message
+    /// Complete TLS (when enabled) and the hello exchange on an inbound stream
+    async fn accept_peer(
+        stream: TcpStream,
+        ctx: &ConnectionContext,
+    ) -> std::result::Result<(PeerHello, Option<String>, BoxedStream), ConnectError> {
+        // Set TCP options
+        stream.set_nodelay(true)
+            .map_err(|e| ConnectError::io(e, "Failed to set TCP nodelay"))?;
+        
+        match &ctx.tls {
+            Some(tls) => {
+                let mut stream = timeout(ctx.config.connection_timeout, tls.acceptor().accept(stream)).await
+                    .map_err(|_| ConnectError::new(FailureKind::Handshake, "TLS handshake timeout"))?
+                    .map_err(|e| ConnectError::io(e, "TLS handshake failed"))?;
+                    
+                let identity = peer_identity(stream.get_ref().1.peer_certificates());
+                let hello = Self::perform_handshake(&mut stream, ctx, identity.as_deref()).await?;
+                Ok((hello, identity, Box::new(stream) as BoxedStream))
+            }
+            None => {
+                let mut stream = stream;
+                let hello = Self::perform_handshake(&mut stream, ctx, None).await?;
+                Ok((hello, None, Box::new(stream) as BoxedStream))
+            }
+        }
+    }
+    
+    /// Perform handshake with remote peer. Over TLS the peer's certificate
+    /// identity must be allowed and match the server id it announces.
+    async fn perform_handshake<S>(
+        stream: &mut S,
+        ctx: &ConnectionContext,
+        identity: Option<&str>,
+    ) -> std::result::Result<PeerHello, ConnectError>
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+    {
+        if let Some(tls) = &ctx.tls {
+            if let Err(reason) = tls.authorize_peer(identity) {
+                // Tell the peer why, so it records an auth failure as well
+                let refusal = NetworkMessage::Error {
+                    code: UNAUTHORIZED_CODE.to_string(),
+                    message: reason.clone(),
+                };
+                if let Ok(encoded) = MessageCodec::encode(&refusal) {
+                    let _ = timeout(ctx.config.connection_timeout, async {
+                        stream.write_all(&encoded).await?;
+                        stream.shutdown().await
+                    }).await;
+                }
+                return Err(ConnectError::new(FailureKind::Auth, reason));
+            }
+        }
+        
+        // Send hello message, announcing what we accept
+        let mut capabilities = vec![
+            "signal".to_string(),
+            "metrics".to_string(),
+            CAPABILITY_BATCH.to_string(),
+            signal_schema_capability(),
+        ];
+        if ctx.config.compression.is_some() {
+            capabilities.push(CAPABILITY_ZSTD.to_string());
+        }
         let hello = NetworkMessage::Hello {
             version: "1.0".to_string(),
-            server_id: server_id.to_string(),
-            capabilities: vec!["signal".to_string(), "metrics".to_string()],
+            server_id: ctx.server_id.clone(),
+            capabilities,
         };
         
-        let encoded = MessageCodec::encode(&hello)?;
+        let encoded = MessageCodec::encode(&hello)
+            .map_err(|e| ConnectError::new(FailureKind::Handshake, e.to_string()))?;
         
-        timeout(config.connection_timeout, async {
-            stream.write_all(&encoded).await
+        timeout(ctx.config.connection_timeout, async {
+            stream.write_all(&encoded).await?;
+            stream.flush().await
         }).await
-            .map_err(|_| Error::Network("Handshake timeout".to_string()))?
-            .map_err(|e| Error::Network(format!("Handshake write error: {}", e)))?;
+            .map_err(|_| ConnectError::new(FailureKind::Handshake, "Handshake timeout"))?
+            .map_err(|e| ConnectError::io(e, "Handshake write error"))?;
             
         // Read response
-        let mut buffer = vec![0u8; config.buffer_size];
-        let n = timeout(config.connection_timeout, async {
-            stream.read(&mut buffer).await
-        }).await
-            .map_err(|_| Error::Network("Handshake response timeout".to_string()))?
-            .map_err(|e| Error::Network(format!("Handshake read error: {}", e)))?;
-            
-        if n == 0 {
-            return Err(Error::Network("Connection closed during handshake".to_string()));
-        }
+        let frame = timeout(ctx.config.connection_timeout, MessageCodec::read_frame(stream, ctx.config.buffer_size)).await
+            .map_err(|_| ConnectError::new(FailureKind::Handshake, "Handshake response timeout"))?
+            .map_err(|e| match e.kind() {
+                std::io::ErrorKind::UnexpectedEof => {
+                    ConnectError::new(FailureKind::Handshake, "Connection closed during handshake")
+                }
+                _ => ConnectError::io(e, "Handshake read error"),
+            })?;
         
         // Decode response
-        let response = MessageCodec::decode(&buffer[..n])?;
+        let response = MessageCodec::decode(&frame)
+            .map_err(|e| ConnectError::new(FailureKind::Handshake, e.to_string()))?;
         
         match response {
-            NetworkMessage::Hello { server_id, .. } => Ok(server_id),
-            _ => Err(Error::Network("Invalid handshake response".to_string())),
+            NetworkMessage::Hello { server_id, capabilities, .. } => {
+                if let Some(id) = identity.filter(|id| *id != server_id) {
+                    return Err(ConnectError::new(
+                        FailureKind::Auth,
+                        format!("peer '{}' presented a certificate for '{}'", server_id, id),
+                    ));
+                }
+                let theirs = peer_signal_schemas(&capabilities);
+                let signal_schema = signal_schema::negotiate(signal_schema::supported(), theirs.clone())
+                    .ok_or_else(|| ConnectError::new(
+                        FailureKind::Handshake,
+                        format!(
+                            "peer '{}' reads signal schema versions {}-{}, which share none with ours ({}-{})",
+                            server_id,
+                            theirs.start(),
+                            theirs.end(),
+                            signal_schema::MIN_SCHEMA_VERSION,
+                            signal_schema::CURRENT_SCHEMA_VERSION
+                        ),
+                    ))?;
+                Ok(PeerHello { server_id, capabilities, signal_schema })
+            }
+            NetworkMessage::Error { code, message } if code == UNAUTHORIZED_CODE => {
+                Err(ConnectError::new(FailureKind::Auth, format!("refused by peer: {}", message)))
+            }
+            _ => Err(ConnectError::new(FailureKind::Handshake, "Invalid handshake response")),
         }
     }
     
+    /// Store an established connection and hand back its read half. Uses
+    /// compression and batching when both we and the peer accept them.
+    fn register(
+        hello: PeerHello,
+        identity: Option<String>,
+        stream: BoxedStream,
+        ctx: &ConnectionContext,
+    ) -> (Arc<Connection>, ReadHalf<BoxedStream>) {
+        let (reader, writer) = tokio::io::split(stream);
+        
+        let compression = ctx.config.compression.filter(|_| hello.offers(CAPABILITY_ZSTD));
+        let batching = !ctx.config.batch_window.is_zero() && hello.offers(CAPABILITY_BATCH);
+        debug!(
+            "Peer {}: compression {}, batching {}, signal schema {}",
+            hello.server_id, compression.is_some(), batching, hello.signal_schema
+        );
+        
+        let peer_id = hello.server_id;
+        let connection = Arc::new(Connection {
+            peer_id: peer_id.clone(),
+            identity,
+            writer: Mutex::new(writer),
+            last_activity: RwLock::new(std::time::Instant::now()),
+            compression,
+            signal_schema: hello.signal_schema,
+            outbox: batching.then(|| Mutex::new(Vec::new())),
+        });
+        
+        ctx.connections.insert(peer_id, connection.clone());
+        (connection, reader)
+    }
+    
     /// Connection read loop
     async fn connection_read_loop(
         connection: Arc<Connection>,
-        connections: Arc<DashMap<String, Arc<Connection>>>,
-        signal_tx: mpsc::Sender<(String, NeuronSignal)>,
-        config: TransportConfig,
-        metrics: Option<Arc<Metrics>>,
+        mut reader: ReadHalf<BoxedStream>,
+        ctx: ConnectionContext,
     ) -> Result<()> {
-        let mut buffer = vec![0u8; config.buffer_size];
         let peer_id = connection.peer_id.clone();
         
         loop {
-            let stream = connection.stream.read().await;
-            
-            // Read with timeout
-            let read_result = timeout(config.io_timeout, async {
-                stream.try_read(&mut buffer)
-            }).await;
-            
-            drop(stream); // Release read lock
-            
-            match read_result {
-                Ok(Ok(0)) => {
-                    info!("Connection closed by peer {}", peer_id);
-                    break;
-                }
-                Ok(Ok(n)) => {
+            match MessageCodec::read_frame(&mut reader, ctx.config.buffer_size).await {
+                Ok(frame) => {
                     // Update last activity
                     *connection.last_activity.write().await = std::time::Instant::now();
                     
                     // Decode message
-                    match MessageCodec::decode(&buffer[..n]) {
-                        Ok(msg) => {
-                            if let Err(e) = Self::handle_message(
-                                msg,
-                                &peer_id,
-                                &signal_tx,
-                                &metrics
-                            ).await {
+                    match MessageCodec::decode_frame(&frame) {
+                        Ok(frame) => {
+                            if let Some(metrics) = &ctx.metrics {
+                                metrics.record_transport_received(frame.uncompressed_len, frame.wire_len, frame.compressed);
+                            }
+                            if let Err(e) = Self::handle_message(frame.message, &connection, &ctx).await {
                                 error!("Failed to handle message: {}", e);
                             }
                         }
@@ -302,23 +570,19 @@ impl TcpTransport {
                         }
                     }
                 }
-                Ok(Err(ref e)) if e.kind() == std::io::ErrorKind::WouldBlock => {
-                    // No data available, sleep briefly
-                    tokio::time::sleep(Duration::from_millis(10)).await;
+                Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+                    info!("Connection closed by peer {}", peer_id);
+                    break;
                 }
-                Ok(Err(e)) => {
+                Err(e) => {
                     error!("Read error from {}: {}", peer_id, e);
                     break;
                 }
-                Err(_) => {
-                    warn!("Read timeout from {}", peer_id);
-                    // Don't break on timeout, connection might still be alive
-                }
             }
         }
         
-        // Remove connection
-        connections.remove(&peer_id);
+        // Remove connection, unless a reconnect already replaced it
+        ctx.connections.remove_if(&peer_id, |_, current| Arc::ptr_eq(current, &connection));
         info!("Removed connection to {}", peer_id);
         
         Ok(())
@@ -327,20 +591,20 @@ impl TcpTransport {
     /// Handle incoming network message
     async fn handle_message(
         msg: NetworkMessage,
-        peer_id: &str,
-        signal_tx: &mpsc::Sender<(String, NeuronSignal)>,
-        metrics: &Option<Arc<Metrics>>,
+        connection: &Connection,
+        ctx: &ConnectionContext,
     ) -> Result<()> {
+        let peer_id = &connection.peer_id;
+        
         match msg {
             NetworkMessage::Signal(signal) => {
-                debug!("Received signal from {}: {:?}", peer_id, signal.signal_id);
-                
-                if let Some(metrics) = metrics {
-                    metrics.record_signal_sent(); // Track as received from network
+                Self::receive_signal(*signal, connection, ctx).await?;
+            }
+            NetworkMessage::Batch { signals } => {
+                debug!("Received batch of {} signals from {}", signals.len(), peer_id);
+                for signal in signals {
+                    Self::receive_signal(signal, connection, ctx).await?;
                 }
-                
-                signal_tx.send((peer_id.to_string(), *signal)).await
-                    .map_err(|_| Error::Communication("Failed to queue signal".to_string()))?;
             }
             NetworkMessage::Ping => {
                 debug!("Received ping from {}", peer_id);
@@ -358,7 +622,38 @@ impl TcpTransport {
         Ok(())
     }
     
-    /// Connect to a remote server
+    /// Queue a signal received from a peer for routing
+    async fn receive_signal(
+        mut signal: NeuronSignal,
+        connection: &Connection,
+        ctx: &ConnectionContext,
+    ) -> Result<()> {
+        let peer_id = &connection.peer_id;
+        debug!("Received signal from {}: {:?}", peer_id, signal.signal_id);
+        
+        // Identity comes from the certificate, never from the payload
+        signal.metadata.remove(PEER_IDENTITY_KEY);
+        if let (Some(identity), Some(tls)) = (&connection.identity, &ctx.tls) {
+            if !tls.is_layer_allowed(identity, &signal.layer_to) {
+                warn!("Dropping signal {} from {}: layer {} not allowed", signal.signal_id, identity, signal.layer_to);
+                if let Some(metrics) = &ctx.metrics {
+                    metrics.record_error("peer_layer_denied");
+                }
+                return Ok(());
+            }
+            signal.metadata.insert(PEER_IDENTITY_KEY.to_string(), identity.clone());
+        }
+        
+        if let Some(metrics) = &ctx.metrics {
+            metrics.record_signal_sent(); // Track as received from network
+        }
+        
+        ctx.signal_tx.send((peer_id.to_string(), signal)).await
+            .map_err(|_| Error::Communication("Failed to queue signal".to_string()))
+    }
+    
+    /// Connect to a remote server. With TLS the peer's certificate must be
+    /// issued for `server_id`.
     pub async fn connect(&self, address: SocketAddr, server_id: &str) -> Result<()> {
         // Check if already connected
         if self.connections.contains_key(server_id) {
@@ -366,46 +661,26 @@ impl TcpTransport {
             return Ok(());
         }
         
-        info!("Connecting to {} at {}", server_id, address);
-        
-        // Connect with timeout
-        let mut stream = timeout(self.config.connection_timeout, async {
-            TcpStream::connect(address).await
-        }).await
-            .map_err(|_| Error::Network(format!("Connection timeout to {}", address)))?
-            .map_err(|e| Error::Network(format!("Failed to connect to {}: {}", address, e)))?;
-            
-        // Set TCP options
-        stream.set_nodelay(true)
-            .map_err(|e| Error::Network(format!("Failed to set TCP nodelay: {}", e)))?;
+        if self.config.tls_enabled && self.tls.is_none() {
+            return Err(Error::InvalidState("TLS certificates not loaded; start the transport first".to_string()));
+        }
         
-        // Perform handshake before creating connection
-        let peer_id = Self::perform_handshake(&mut stream, &self.config, &self.server_id).await?;
+        info!("Connecting to {} at {}", server_id, address);
         
-        // Create connection with the stream
-        let connection = Arc::new(Connection {
-            peer_id: peer_id.clone(),
-            stream: Arc::new(RwLock::new(stream)),
-            last_activity: RwLock::new(std::time::Instant::now()),
-        });
+        let ctx = self.context();
+        let (hello, identity, stream) = match Self::dial(address, server_id, &ctx).await {
+            Ok(dialed) => dialed,
+            Err(e) => {
+                ctx.record_failure(&e);
+                return Err(e.into());
+            }
+        };
         
-        // Store connection
-        self.connections.insert(peer_id, connection.clone());
+        let (connection, reader) = Self::register(hello, identity, stream, &ctx);
         
         // Start read loop
-        let connections = self.connections.clone();
-        let signal_tx = self.signal_tx.clone();
-        let config = self.config.clone();
-        let metrics = self.metrics.clone();
-        
         tokio::spawn(async move {
-            if let Err(e) = Self::connection_read_loop(
-                connection,
-                connections,
-                signal_tx,
-                config,
-                metrics
-            ).await {
+            if let Err(e) = Self::connection_read_loop(connection, reader, ctx).await {
                 error!("Connection read loop error: {}", e);
             }
         });
@@ -413,24 +688,87 @@ impl TcpTransport {
         Ok(())
     }
     
-    /// Send a signal to a remote server
+    /// Open TCP (and TLS when enabled) to a peer and perform the handshake
+    async fn dial(
+        address: SocketAddr,
+        server_id: &str,
+        ctx: &ConnectionContext,
+    ) -> std::result::Result<(PeerHello, Option<String>, BoxedStream), ConnectError> {
+        // Connect with timeout
+        let stream = timeout(ctx.config.connection_timeout, TcpStream::connect(address)).await
+            .map_err(|_| ConnectError::new(FailureKind::Network, format!("Connection timeout to {}", address)))?
+            .map_err(|e| ConnectError::new(FailureKind::Network, format!("Failed to connect to {}: {}", address, e)))?;
+            
+        // Set TCP options
+        stream.set_nodelay(true)
+            .map_err(|e| ConnectError::io(e, "Failed to set TCP nodelay"))?;
+        
+        match &ctx.tls {
+            Some(tls) => {
+                let connector = tls.connector()
+                    .map_err(|e| ConnectError::new(FailureKind::Handshake, e.to_string()))?;
+                let mut stream = timeout(ctx.config.connection_timeout, connector.connect(server_name(server_id)?, stream)).await
+                    .map_err(|_| ConnectError::new(FailureKind::Handshake, "TLS handshake timeout"))?
+                    .map_err(|e| ConnectError::io(e, "TLS handshake failed"))?;
+                    
+                let identity = peer_identity(stream.get_ref().1.peer_certificates());
+                let hello = Self::perform_handshake(&mut stream, ctx, identity.as_deref()).await?;
+                Ok((hello, identity, Box::new(stream) as BoxedStream))
+            }
+            None => {
+                let mut stream = stream;
+                let hello = Self::perform_handshake(&mut stream, ctx, None).await?;
+                Ok((hello, None, Box::new(stream) as BoxedStream))
+            }
+        }
+    }
+    
+    /// Send a signal to a remote server. With batching on the signal may
+    /// only be queued when this returns; a failed batch is logged and
+    /// counted as a `transport_batch_send` error.
     pub async fn send_signal(&self, server_id: &str, signal: NeuronSignal) -> Result<()> {
+        if self.chaos.as_ref().is_some_and(|chaos| chaos.drops_message(server_id)) {
+            // Lost on the wire: the sender never hears of it
+            debug!("Chaos dropped signal {} to {}", signal.signal_id, server_id);
+            return Ok(());
+        }
         let connection = self.connections.get(server_id)
+            .map(|entry| entry.value().clone())
             .ok_or_else(|| Error::Network(format!("Not connected to {}", server_id)))?;
-            
-        let msg = NetworkMessage::Signal(Box::new(signal));
-        let encoded = MessageCodec::encode(&msg)?;
-        
-        let stream = connection.stream.write().await;
-        
-        timeout(self.config.io_timeout, async {
-            stream.try_write(&encoded)
-        }).await
-            .map_err(|_| Error::Network("Send timeout".to_string()))?
-            .map_err(|e| Error::Network(format!("Send error: {}", e)))?;
-            
-        // Update last activity
-        *connection.last_activity.write().await = std::time::Instant::now();
+        let signal = connection.for_peer(signal)?;
+        let io_timeout = self.config.io_timeout;
+        let metrics = self.metrics.as_deref();
+        
+        match &connection.outbox {
+            Some(outbox) => {
+                let urgent = is_urgent(&signal);
+                let mut queued = outbox.lock().await;
+                queued.push(signal);
+                
+                if urgent || queued.len() >= self.config.batch_max_signals {
+                    // Whatever is queued goes first, keeping signals in order
+                    connection.write_queued(&mut queued, io_timeout, metrics).await?;
+                } else if queued.len() == 1 {
+                    // First signal of a batch: send the batch when the window closes
+                    let connection = connection.clone();
+                    let window = self.config.batch_window;
+                    let metrics = self.metrics.clone();
+                    tokio::spawn(async move {
+                        tokio::time::sleep(window).await;
+                        if let Err(e) = connection.flush(io_timeout, metrics.as_deref()).await {
+                            error!("Failed to send batch to {}: {}", connection.peer_id, e);
+                            if let Some(metrics) = &metrics {
+                                metrics.record_error("transport_batch_send");
+                            }
+                        }
+                    });
+                }
+            }
+            None => {
+                let msg = NetworkMessage::Signal(Box::new(signal));
+                connection.write_message(&msg, io_timeout, metrics).await?;
+            }
+        }
         
         if let Some(metrics) = &self.metrics {
             metrics.record_signal_sent();
@@ -491,7 +829,7 @@ impl TcpTransport {
         // Close all connections
         for entry in self.connections.iter() {
             let connection = entry.value();
-            if connection.stream.write().await.shutdown().await.is_ok() {
+            if connection.writer.lock().await.shutdown().await.is_ok() {
                 debug!("Closed connection to {}", entry.key());
             }
         }
@@ -0,0 +1,271 @@
+//! TLS and mutual TLS for inter-server connections
+
+use std::fmt;
+use std::fs::File;
+use std::io::BufReader;
+use std::sync::Arc;
+
+use parking_lot::RwLock;
+use tokio_rustls::rustls::{
+    self, server::AllowAnyAuthenticatedClient, AlertDescription, Certificate, PrivateKey,
+    RootCertStore, ServerName,
+};
+use tokio_rustls::{TlsAcceptor, TlsConnector};
+use tracing::info;
+use x509_parser::prelude::{FromDer, GeneralName, X509Certificate};
+
+use hal9_core::{Error, Result};
+use crate::network::tcp_transport::TransportConfig;
+
+/// Signal metadata key carrying the certificate identity of the sending server
+pub const PEER_IDENTITY_KEY: &str = hal9_core::metadata_schema::keys::network::PEER_IDENTITY;
+
+/// Layer entry that allows a peer to address every layer
+pub const ANY_LAYER: &str = "*";
+
+/// Why a connection to or from a peer could not be established
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FailureKind {
+    /// TCP connect, reset or unexpected close
+    Network,
+    /// TLS or HAL9 hello negotiation failed
+    Handshake,
+    /// Certificate rejected, or peer not allowed
+    Auth,
+}
+
+impl FailureKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FailureKind::Network => "network",
+            FailureKind::Handshake => "handshake",
+            FailureKind::Auth => "auth",
+        }
+    }
+
+    /// Classify an I/O error raised while talking to a peer
+    pub fn classify(err: &std::io::Error) -> Self {
+        match err.get_ref().and_then(|inner| inner.downcast_ref::<rustls::Error>()) {
+            Some(rustls::Error::InvalidCertificate(_))
+            | Some(rustls::Error::NoCertificatesPresented) => FailureKind::Auth,
+            Some(rustls::Error::AlertReceived(alert)) if is_auth_alert(alert) => FailureKind::Auth,
+            Some(_) => FailureKind::Handshake,
+            None => FailureKind::Network,
+        }
+    }
+}
+
+fn is_auth_alert(alert: &AlertDescription) -> bool {
+    matches!(
+        alert,
+        AlertDescription::BadCertificate
+            | AlertDescription::UnsupportedCertificate
+            | AlertDescription::CertificateRevoked
+            | AlertDescription::CertificateExpired
+            | AlertDescription::CertificateUnknown
+            | AlertDescription::CertificateRequired
+            | AlertDescription::UnknownCA
+            | AlertDescription::AccessDenied
+    )
+}
+
+/// A failed connection attempt, tagged for metrics
+#[derive(Debug)]
+pub struct ConnectError {
+    pub kind: FailureKind,
+    pub message: String,
+}
+
+impl ConnectError {
+    pub fn new(kind: FailureKind, message: impl Into<String>) -> Self {
+        Self { kind, message: message.into() }
+    }
+
+    pub fn io(err: std::io::Error, context: &str) -> Self {
+        Self::new(FailureKind::classify(&err), format!("{}: {}", context, err))
+    }
+}
+
+impl fmt::Display for ConnectError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} failure: {}", self.kind.as_str(), self.message)
+    }
+}
+
+impl From<ConnectError> for Error {
+    fn from(err: ConnectError) -> Self {
+        Error::Network(err.to_string())
+    }
+}
+
+/// Holds the live rustls configuration. Certificates are re-read on
+/// `reload`; connections already established keep their session.
+pub struct TlsManager {
+    config: TransportConfig,
+    acceptor: RwLock<TlsAcceptor>,
+    connector: RwLock<Option<TlsConnector>>,
+}
+
+impl TlsManager {
+    /// Load certificates named in the transport config
+    pub fn load(config: &TransportConfig) -> Result<Self> {
+        let (acceptor, connector) = Self::build(config)?;
+        Ok(Self {
+            config: config.clone(),
+            acceptor: RwLock::new(acceptor),
+            connector: RwLock::new(connector),
+        })
+    }
+
+    /// Re-read certificate, key and CA files. On error the previous
+    /// configuration stays active.
+    pub fn reload(&self) -> Result<()> {
+        let (acceptor, connector) = Self::build(&self.config)?;
+        *self.acceptor.write() = acceptor;
+        *self.connector.write() = connector;
+        info!("Reloaded network TLS certificates");
+        Ok(())
+    }
+
+    pub fn acceptor(&self) -> TlsAcceptor {
+        self.acceptor.read().clone()
+    }
+
+    /// Connector for outbound connections; requires a CA to verify peers
+    pub fn connector(&self) -> Result<TlsConnector> {
+        self.connector.read().clone()
+            .ok_or_else(|| Error::Config("network.tls_ca is required for outbound TLS connections".to_string()))
+    }
+
+    /// Whether the server requests and verifies client certificates
+    pub fn mutual(&self) -> bool {
+        self.config.tls_require_client_cert
+    }
+
+    /// Check a peer's certificate identity against the configured peers.
+    /// With no peers configured every verified peer is accepted.
+    pub fn authorize_peer(&self, identity: Option<&str>) -> std::result::Result<(), String> {
+        if self.config.peer_allowed_layers.is_empty() {
+            return Ok(());
+        }
+        match identity {
+            Some(id) if self.config.peer_allowed_layers.contains_key(id) => Ok(()),
+            Some(id) => Err(format!("unknown peer '{}'", id)),
+            None => Err("peer presented no certificate identity".to_string()),
+        }
+    }
+
+    /// Whether a peer may send signals addressed to `layer`
+    pub fn is_layer_allowed(&self, identity: &str, layer: &str) -> bool {
+        if self.config.peer_allowed_layers.is_empty() {
+            return true;
+        }
+        self.config.peer_allowed_layers.get(identity)
+            .map(|layers| layers.iter().any(|l| l == ANY_LAYER || l == layer))
+            .unwrap_or(false)
+    }
+
+    fn build(config: &TransportConfig) -> Result<(TlsAcceptor, Option<TlsConnector>)> {
+        let cert_path = config.tls_cert_path.as_deref()
+            .ok_or_else(|| Error::Config("network.tls_cert is required when TLS is enabled".to_string()))?;
+        let key_path = config.tls_key_path.as_deref()
+            .ok_or_else(|| Error::Config("network.tls_key is required when TLS is enabled".to_string()))?;
+
+        let certs = load_certs(cert_path)?;
+        let key = load_key(key_path)?;
+        let roots = config.tls_ca_path.as_deref().map(load_roots).transpose()?;
+
+        let builder = rustls::ServerConfig::builder().with_safe_defaults();
+        let builder = if config.tls_require_client_cert {
+            let roots = roots.clone()
+                .ok_or_else(|| Error::Config("network.tls_ca is required for mutual TLS".to_string()))?;
+            builder.with_client_cert_verifier(AllowAnyAuthenticatedClient::new(roots).boxed())
+        } else {
+            builder.with_no_client_auth()
+        };
+        let server_config = builder.with_single_cert(certs.clone(), key.clone())
+            .map_err(|e| Error::Config(format!("Invalid TLS certificate or key: {}", e)))?;
+
+        // Present our certificate when dialing out so peers running
+        // mutual TLS can identify us
+        let connector = match roots {
+            Some(roots) => {
+                let client_config = rustls::ClientConfig::builder()
+                    .with_safe_defaults()
+                    .with_root_certificates(roots)
+                    .with_client_auth_cert(certs, key)
+                    .map_err(|e| Error::Config(format!("Invalid TLS client certificate: {}", e)))?;
+                Some(TlsConnector::from(Arc::new(client_config)))
+            }
+            None => None,
+        };
+
+        Ok((TlsAcceptor::from(Arc::new(server_config)), connector))
+    }
+}
+
+/// TLS server name for dialing a peer; peer certificates are issued for
+/// their server id
+pub fn server_name(server_id: &str) -> std::result::Result<ServerName, ConnectError> {
+    ServerName::try_from(server_id)
+        .map_err(|_| ConnectError::new(FailureKind::Handshake, format!("'{}' is not a valid TLS server name", server_id)))
+}
+
+/// Identity of a peer certificate: the first DNS subject alternative
+/// name, falling back to the subject common name
+pub fn peer_identity(certs: Option<&[Certificate]>) -> Option<String> {
+    let leaf = certs?.first()?;
+    let (_, cert) = X509Certificate::from_der(&leaf.0).ok()?;
+
+    if let Ok(Some(san)) = cert.subject_alternative_name() {
+        for name in &san.value.general_names {
+            if let GeneralName::DNSName(dns) = name {
+                return Some(dns.to_string());
+            }
+        }
+    }
+
+    let common_name = cert.subject().iter_common_name()
+        .next()
+        .and_then(|cn| cn.as_str().ok())
+        .map(str::to_string);
+    common_name
+}
+
+fn load_certs(path: &str) -> Result<Vec<Certificate>> {
+    let file = File::open(path)
+        .map_err(|e| Error::Config(format!("Failed to open certificate {}: {}", path, e)))?;
+    let certs = rustls_pemfile::certs(&mut BufReader::new(file))
+        .map_err(|e| Error::Config(format!("Failed to parse certificate {}: {}", path, e)))?;
+    if certs.is_empty() {
+        return Err(Error::Config(format!("No certificates found in {}", path)));
+    }
+    Ok(certs.into_iter().map(Certificate).collect())
+}
+
+fn load_key(path: &str) -> Result<PrivateKey> {
+    let file = File::open(path)
+        .map_err(|e| Error::Config(format!("Failed to open private key {}: {}", path, e)))?;
+    let mut reader = BufReader::new(file);
+
+    loop {
+        match rustls_pemfile::read_one(&mut reader)
+            .map_err(|e| Error::Config(format!("Failed to parse private key {}: {}", path, e)))?
+        {
+            Some(rustls_pemfile::Item::PKCS8Key(key))
+            | Some(rustls_pemfile::Item::RSAKey(key))
+            | Some(rustls_pemfile::Item::ECKey(key)) => return Ok(PrivateKey(key)),
+            Some(_) => continue,
+            None => return Err(Error::Config(format!("No private key found in {}", path))),
+        }
+    }
+}
+
+fn load_roots(path: &str) -> Result<RootCertStore> {
+    let mut roots = RootCertStore::empty();
+    for cert in load_certs(path)? {
+        roots.add(&cert)
+            .map_err(|e| Error::Config(format!("Invalid CA certificate in {}: {}", path, e)))?;
+    }
+    Ok(roots)
+}
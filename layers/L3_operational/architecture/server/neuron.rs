@@ -4,7 +4,7 @@ use async_trait::async_trait;
 use dashmap::DashMap;
 use std::sync::Arc;
 use std::collections::HashMap;
-use tokio::sync::RwLock;
+use tokio::sync::{mpsc, RwLock};
 use tracing::{debug, error, info, warn, instrument};
 use crate::{log_performance, logging::neuron_span};
 use chrono::Utc;
@@ -17,18 +17,99 @@ use hal9_core::{
     neuron::{NeuronState, NeuronHealth},
     mcp::{ToolRegistry, FilesystemReadTool, FilesystemWriteTool, 
           ShellTool, WebFetchTool},
-    memory::{MemoryStore, MemoryBuilder, MemoryType},
+    memory::{MemoryBuilder, MemoryType, MemoryEntry, MemorySearch, MemoryNamespace, NamespacedMemory},
+    metadata_schema::keys,
     learning::{ErrorGradient, GradientCalculator, PromptAdjuster, 
                PatternMatcher},
 };
 use md5;
 
 use crate::{
+    approvals::{self, ApprovalSettings},
+    chain_tracker::{mark_gradient, PARENT_ID_KEY},
+    chaos::ChaosEngine,
+    citations::{self, CitationTrailer, CitedMemory},
     claude::ClaudeInterface,
-    circuit_breaker::{CircuitBreaker, CircuitBreakerConfig},
+    local_model,
+    concurrency::{Admission, ConcurrencyConfig, ConcurrencyLimiter, DeadLetterQueue, Reservation},
+    fan_out::{FanOutPolicy, FanOutTruncation},
+    circuit_breaker::{CircuitBreaker, CircuitBreakerConfig, CircuitState},
+    isolation::NeuronWorker,
+    neuron_changes::{ChangeFeed, NeuronChanges},
+    output_format::{FormatReport, FormatRequest},
+    prompt_composition::{
+        split_adjusted, ComposedPrompt, PromptAccounting, PromptComposition, PromptSection, PROMPT_SECTIONS_KEY,
+        PROMPT_TOKENIZER_KEY,
+    },
+    read_only::{MemoryWrite, MemoryWriteAdmission, ReadOnlyGate},
+    recovery::RecoveryPlaybook,
+    router::policy::{RoutingPolicy, ROUTE_KEYS},
+    sessions::SESSION_CONTEXT_KEY,
+    timeouts::{TimeoutDecision, TimeoutPolicy, TimeoutSource},
+    tool_sandbox::ToolSandbox,
     performance::{ResponseCache, PerformanceMonitor},
+    validation::{retry_prompt, ValidationPipeline, ValidationReport},
+    warm_pool::{PooledInstance, WarmPool},
 };
 
+/// Prefixes of the lines that carry a directive in a neuron's response
+const DIRECTIVES: [&str; 7] = [
+    "FORWARD_TO:",
+    "BACKWARD_TO:",
+    "ERROR_TYPE:",
+    "CONTENT:",
+    "TOOL:",
+    "CITATIONS:",
+    approvals::APPROVAL_DIRECTIVE,
+];
+
+fn is_directive(line: &str) -> bool {
+    DIRECTIVES.iter().any(|directive| line.starts_with(directive))
+}
+
+/// The forward instruction of a neuron's response: the neurons named on its
+/// first `FORWARD_TO:` line and the lines after its `CONTENT:` line, up to
+/// the next directive or the end
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ForwardInstruction {
+    pub targets: Vec<String>,
+    pub content: String,
+}
+
+impl ForwardInstruction {
+    /// The forward instruction of a finished response, if it has one
+    pub fn parse(response: &str) -> Option<Self> {
+        let forward_line = response.lines().find(|l| l.starts_with("FORWARD_TO:"))?;
+        let targets = forward_line
+            .strip_prefix("FORWARD_TO:")
+            .unwrap_or("")
+            .split(',')
+            .map(|s| s.trim())
+            .filter(|s| !s.is_empty())
+            .map(String::from)
+            .collect();
+        let content = response.lines()
+            .skip_while(|l| !l.starts_with("CONTENT:"))
+            .skip(1)
+            .take_while(|l| !is_directive(l))
+            .collect::<Vec<_>>()
+            .join("\n");
+        Some(Self { targets, content })
+    }
+    
+    /// The forward instruction of a response still being produced, once
+    /// nothing more can change it: its `FORWARD_TO:` line is complete and a
+    /// later directive line has closed its content
+    pub fn parse_partial(partial: &str) -> Option<Self> {
+        // The last line may still grow
+        let complete = &partial[..partial.rfind('\n')? + 1];
+        let lines: Vec<&str> = complete.lines().collect();
+        let content_at = lines.iter().position(|l| l.starts_with("CONTENT:"))?;
+        lines[content_at + 1..].iter().find(|l| is_directive(l))?;
+        Self::parse(complete)
+    }
+}
+
 /// A managed neuron that wraps a Claude instance
 pub struct ManagedNeuron {
     pub id: String,
@@ -42,10 +123,53 @@ pub struct ManagedNeuron {
     response_cache: Option<ResponseCache>,
     performance_monitor: PerformanceMonitor,
     tool_registry: ToolRegistry,
-    memory_store: Option<Arc<dyn MemoryStore>>,
+    memory: Option<Arc<NamespacedMemory>>,
+    /// Holds or skips memory writes while the server is read-only
+    read_only: Option<Arc<ReadOnlyGate>>,
     prompt_adjuster: Option<RwLock<PromptAdjuster>>,
     pattern_matcher: Option<RwLock<PatternMatcher>>,
     gradient_calculator: Option<GradientCalculator>,
+    validation: Option<ValidationPipeline>,
+    /// Reports of validated responses by signal, taken by `parse_response`
+    validation_reports: DashMap<Uuid, ValidationReport>,
+    /// Cheaper model rewriting outputs that miss their requested format
+    reformatter: Option<Box<dyn ClaudeInterface>>,
+    max_reformat_retries: u32,
+    /// Format outcomes of terminal outputs by signal, taken by the router
+    format_reports: DashMap<Uuid, FormatReport>,
+    recovery: Option<Arc<RecoveryPlaybook>>,
+    /// Whether every forward response needs approval
+    approval: ApprovalSettings,
+    /// Picks the target of forwards that name no neuron
+    routing: Option<Arc<RoutingPolicy>>,
+    concurrency: Arc<ConcurrencyLimiter>,
+    /// Worker process signals are processed in, when isolated
+    worker: Option<Arc<NeuronWorker>>,
+    /// Timeouts of provider calls, shared by every neuron of the server
+    timeouts: Arc<TimeoutPolicy>,
+    /// Timeouts of the calls answering each signal, taken by `parse_response`
+    timeout_decisions: DashMap<Uuid, TimeoutDecision>,
+    /// Branch and node limits of the chains this neuron forwards in
+    fan_out: Arc<FanOutPolicy>,
+    /// Branches `parse_response` dropped by signal, taken by the router
+    fan_out_truncations: DashMap<Uuid, FanOutTruncation>,
+    /// Ledgers of the chains this neuron's tool calls are charged to
+    tool_sandbox: Option<Arc<ToolSandbox>>,
+    /// Injected faults applied to signals before processing
+    chaos: Option<Arc<ChaosEngine>>,
+    /// Where the first provider call answering a signal streams its
+    /// response, taken when the call is made
+    stream_sinks: DashMap<Uuid, mpsc::UnboundedSender<String>>,
+    /// Memory entries recalled into the prompt for each signal, taken once
+    /// the prompt is built
+    injected_memories: DashMap<Uuid, Vec<CitedMemory>>,
+    /// Memory entries each response cited, taken by the router
+    citations: DashMap<Uuid, Vec<CitedMemory>>,
+    /// Counts the sections of the prompts this neuron sends
+    prompt_accounting: Option<Arc<PromptAccounting>>,
+    /// Prompt tokens by section of the call answering each signal, taken
+    /// by `parse_response`
+    prompt_compositions: DashMap<Uuid, PromptComposition>,
 }
 
 #[derive(Default)]
@@ -64,6 +188,9 @@ impl ManagedNeuron {
     ) -> Result<Self> {
         let layer = Layer::from_str(&config.layer)
             .ok_or_else(|| Error::Config(format!("Invalid layer: {}", config.layer)))?;
+        let concurrency = Arc::new(ConcurrencyLimiter::new(ConcurrencyConfig::for_neuron(&config, layer)?));
+        let approval = ApprovalSettings::for_neuron(&config)?;
+        let routing = RoutingPolicy::for_neuron(&config)?.map(Arc::new);
             
         let circuit_breaker = CircuitBreaker::new(
             format!("neuron-{}", config.id),
@@ -207,21 +334,497 @@ impl ManagedNeuron {
             response_cache,
             performance_monitor: PerformanceMonitor::new(),
             tool_registry,
-            memory_store: None,
+            memory: None,
+            read_only: None,
             prompt_adjuster: None,
             pattern_matcher: None,
             gradient_calculator: None,
+            validation: None,
+            validation_reports: DashMap::new(),
+            reformatter: None,
+            max_reformat_retries: 0,
+            format_reports: DashMap::new(),
+            recovery: None,
+            approval,
+            routing,
+            concurrency,
+            worker: None,
+            timeouts: Arc::new(TimeoutPolicy::default()),
+            timeout_decisions: DashMap::new(),
+            fan_out: Arc::new(FanOutPolicy::default()),
+            fan_out_truncations: DashMap::new(),
+            tool_sandbox: None,
+            chaos: None,
+            stream_sinks: DashMap::new(),
+            injected_memories: DashMap::new(),
+            citations: DashMap::new(),
+            prompt_accounting: None,
+            prompt_compositions: DashMap::new(),
         })
     }
     
     /// Set metrics collector
     pub fn set_metrics(&mut self, metrics: Arc<crate::metrics::Metrics>) {
+        metrics.register_neuron_queue(&self.id, self.concurrency.clone());
         self.metrics = Some(metrics);
     }
+
+    /// Metrics collector, if one is set
+    pub fn metrics(&self) -> Option<&Arc<crate::metrics::Metrics>> {
+        self.metrics.as_ref()
+    }
+
+    /// Token usage of the neuron's most recent Claude request
+    pub fn last_token_usage(&self) -> Option<crate::claude::TokenUsage> {
+        match &self.worker {
+            Some(worker) => worker.last_token_usage(),
+            None => self.claude.last_token_usage(),
+        }
+    }
+    
+    /// Processing and queue limits of this neuron
+    pub fn concurrency(&self) -> &ConcurrencyLimiter {
+        &self.concurrency
+    }
+    
+    /// State of the circuit breaker guarding this neuron's provider calls
+    pub async fn circuit_state(&self) -> CircuitState {
+        self.circuit_breaker.state().await
+    }
+    
+    /// Replace the thresholds of the circuit breaker guarding this neuron's
+    /// provider calls
+    pub fn set_circuit_breaker(&mut self, config: CircuitBreakerConfig) {
+        self.circuit_breaker = CircuitBreaker::new(format!("neuron-{}", self.id), config);
+    }
+    
+    /// Take a processing slot for a signal, waiting in the queue if one is
+    /// not free. Overflow is decided by the neuron's overflow mode.
+    pub async fn admit(&self) -> Admission {
+        self.admit_reserved(None).await
+    }
+    
+    /// Take a processing slot for a signal in the room reserved for it
+    pub async fn admit_reserved(&self, reservation: Option<Reservation>) -> Admission {
+        let admission = self.concurrency.admit_reserved(reservation).await;
+        if let Some(metrics) = &self.metrics {
+            match &admission {
+                Admission::Rejected => metrics.record_error("neuron_queue_rejected"),
+                Admission::Shed(_) => metrics.record_error("neuron_queue_shed"),
+                Admission::Admitted(_) => {}
+            }
+        }
+        admission
+    }
+    
+    /// Set namespaced memory
+    pub fn set_memory(&mut self, memory: Arc<NamespacedMemory>) {
+        self.memory = Some(memory);
+    }
+    
+    /// Set the gate memory writes pass while the server is read-only
+    pub fn set_read_only(&mut self, gate: Arc<ReadOnlyGate>) {
+        self.read_only = Some(gate);
+    }
+    
+    /// Set the policy choosing the timeout of each provider call
+    pub fn set_timeouts(&mut self, timeouts: Arc<TimeoutPolicy>) {
+        self.timeouts = timeouts;
+    }
+    
+    /// Set the accounting counting the sections of this neuron's prompts
+    pub fn set_prompt_accounting(&mut self, accounting: Arc<PromptAccounting>) {
+        self.prompt_accounting = Some(accounting);
+    }
+    
+    /// Set the branch and node limits applied to this neuron's responses
+    pub fn set_fan_out(&mut self, fan_out: Arc<FanOutPolicy>) {
+        self.fan_out = fan_out;
+    }
+    
+    /// Set the sandbox charging this neuron's tool calls to their chains
+    pub fn set_tool_sandbox(&mut self, sandbox: Arc<ToolSandbox>) {
+        self.tool_sandbox = Some(sandbox);
+    }
+    
+    /// Run a tool for `signal`, within its chain's limits when sandboxed
+    async fn execute_tool(&self, signal: &NeuronSignal, tool_name: &str, params: Value) -> Result<Value> {
+        match &self.tool_sandbox {
+            Some(sandbox) => sandbox.execute(&self.tool_registry, signal, tool_name, params).await,
+            None => self.tool_registry.execute(tool_name, params).await,
+        }
+    }
+    
+    /// Take the branches dropped from this neuron's response to
+    /// `signal_id`, if its fan-out was over a limit
+    pub fn take_fan_out_truncation(&self, signal_id: &Uuid) -> Option<FanOutTruncation> {
+        self.fan_out_truncations.remove(signal_id).map(|(_, truncation)| truncation)
+    }
+    
+    /// Process a signal, streaming the response of its provider call to
+    /// `partial` as it is produced. Cached responses, retries after failed
+    /// validation and tool follow-ups are not streamed, nor is anything
+    /// processed in a worker.
+    pub async fn process_streaming(
+        &self,
+        signal: &NeuronSignal,
+        partial: mpsc::UnboundedSender<String>,
+    ) -> Result<String> {
+        self.stream_sinks.insert(signal.signal_id, partial);
+        let result = self.process_signal(signal).await;
+        self.stream_sinks.remove(&signal.signal_id);
+        result
+    }
+    
+    /// Set the engine whose injected delays and kills apply to this neuron
+    pub fn set_chaos(&mut self, chaos: Arc<ChaosEngine>) {
+        self.chaos = Some(chaos);
+    }
+    
+    /// Take the timeout of the last call answering `signal_id`, e.g. to
+    /// record it on the error signal of a failed one
+    pub fn take_timeout_decision(&self, signal_id: &Uuid) -> Option<TimeoutDecision> {
+        self.timeout_decisions.remove(signal_id).map(|(_, decision)| decision)
+    }
+    
+    /// Set validators run on responses before they are forwarded
+    pub fn set_validation(&mut self, validation: ValidationPipeline) {
+        self.validation = Some(validation);
+    }
+    
+    /// Validate `response`, re-prompting with the failure reason until it
+    /// passes or retries run out. Retries go straight to Claude; tool
+    /// requests in a retried response are not executed.
+    async fn validate_response(&self, signal: &NeuronSignal, prompt: &str, mut response: String) -> Result<String> {
+        let validation = match &self.validation {
+            Some(validation) => validation,
+            None => return Ok(response),
+        };
+        
+        let mut retries = 0;
+        let mut last_failure = None;
+        loop {
+            let results = validation.validate(&response);
+            if let Some(metrics) = &self.metrics {
+                for result in &results {
+                    metrics.record_validation(result.validator, result.failure.is_none());
+                }
+            }
+            
+            let failure = results.into_iter()
+                .find_map(|r| r.failure.map(|reason| format!("{}: {}", r.validator, reason)));
+            let reason = match failure {
+                None => {
+                    self.validation_reports.insert(signal.signal_id, ValidationReport {
+                        passed: true,
+                        reason: last_failure,
+                        retries,
+                    });
+                    return Ok(response);
+                }
+                Some(reason) => reason,
+            };
+            
+            if retries >= validation.max_retries() {
+                warn!(
+                    target: "neuron.validation",
+                    neuron_id = %self.id,
+                    retries = retries,
+                    reason = %reason,
+                    "Response failed validation"
+                );
+                return Err(Error::Processing(format!(
+                    "Neuron {} output failed validation after {} retries: {}",
+                    self.id, retries, reason
+                )));
+            }
+            
+            retries += 1;
+            debug!(
+                target: "neuron.validation",
+                neuron_id = %self.id,
+                retry = retries,
+                reason = %reason,
+                "Retrying after failed validation"
+            );
+            if let Some(metrics) = &self.metrics {
+                metrics.record_validation_retry();
+            }
+            response = self.claude.send_message(&retry_prompt(prompt, &response, &reason)).await?;
+            last_failure = Some(reason);
+        }
+    }
+    
+    /// Set the model rewriting outputs that miss their requested format
+    pub fn set_reformatter(&mut self, reformatter: Box<dyn ClaudeInterface>, max_retries: u32) {
+        self.reformatter = Some(reformatter);
+        self.max_reformat_retries = max_retries;
+    }
+    
+    /// Prompt sent to Claude for `signal`, by section
+    async fn prompt_for(&self, signal: &NeuronSignal, output_format: Option<&FormatRequest>) -> ComposedPrompt {
+        // Get base prompt (potentially adjusted by learning), else the
+        // configured one. What learning changed counts as corrections.
+        let mut prompt = ComposedPrompt::new();
+        if let Some(adjuster) = &self.prompt_adjuster {
+            let adjuster = adjuster.read().await;
+            let (kept, changed, rest) = split_adjusted(adjuster.base_prompt(), adjuster.get_current_prompt());
+            prompt.push(PromptSection::LayerInstructions, kept);
+            prompt.push(PromptSection::Corrections, changed);
+            prompt.push(PromptSection::LayerInstructions, rest);
+        } else if let Some(system_prompt) = &self.config.system_prompt {
+            prompt.push(PromptSection::LayerInstructions, system_prompt.as_str());
+        } else {
+            prompt.append(self.format_prompt(signal).await);
+        }
+        
+        // Format prompt with signal context
+        prompt.push(PromptSection::LayerInstructions, "\n\n");
+        prompt.append(self.format_prompt(signal).await);
+        
+        // A chain's final output comes in the format its submitter asked for
+        if let Some(request) = output_format {
+            prompt.push(PromptSection::LayerInstructions, "\n\n");
+            prompt.push(PromptSection::LayerInstructions, request.instructions());
+        }
+        prompt
+    }
+    
+    /// Prompt this neuron would send for `signal`, for recovery steps that
+    /// ask another model in its place
+    pub async fn recovery_prompt(&self, signal: &NeuronSignal) -> String {
+        let prompt = self.prompt_for(signal, self.output_format(signal).as_ref()).await;
+        self.injected_memories.remove(&signal.signal_id);
+        prompt.text()
+    }
+    
+    /// Set the playbook run when this neuron fails a signal
+    pub fn set_recovery(&mut self, recovery: RecoveryPlaybook) {
+        self.recovery = Some(Arc::new(recovery));
+    }
     
-    /// Set memory store
-    pub fn set_memory_store(&mut self, memory_store: Arc<dyn MemoryStore>) {
-        self.memory_store = Some(memory_store);
+    /// Playbook run when this neuron fails a signal
+    pub fn recovery(&self) -> Option<Arc<RecoveryPlaybook>> {
+        self.recovery.clone()
+    }
+    
+    /// Policy routing this neuron's forwards that name no neuron
+    pub fn routing(&self) -> Option<Arc<RoutingPolicy>> {
+        self.routing.clone()
+    }
+    
+    /// Whether every response of this neuron to a forward signal is held
+    /// for approval
+    pub fn requires_approval(&self) -> bool {
+        self.approval.required
+    }
+    
+    /// Why `response` must be approved before it continues downstream: the
+    /// reason on its `REQUIRES_APPROVAL:` line, or this neuron requiring
+    /// approval of everything it forwards
+    pub fn approval_reason(&self, response: &str) -> Option<String> {
+        approvals::requested(response).or_else(|| {
+            self.approval.required.then(|| format!("Neuron {} requires approval of its outputs", self.id))
+        })
+    }
+    
+    /// Whether this neuron ends chains: it has no neurons to forward to, so
+    /// its output is a chain's final output
+    pub fn is_terminal(&self) -> bool {
+        self.config.forward_connections.is_empty()
+    }
+    
+    /// Take the format outcome of this neuron's output for `signal_id`, if a
+    /// format was requested
+    pub fn take_format_report(&self, signal_id: &Uuid) -> Option<FormatReport> {
+        self.format_reports.remove(signal_id).map(|(_, report)| report)
+    }
+    
+    /// Take the validation outcome of this neuron's response to `signal_id`
+    /// before `parse_response` applies it
+    pub fn take_validation_report(&self, signal_id: &Uuid) -> Option<ValidationReport> {
+        self.validation_reports.remove(signal_id).map(|(_, report)| report)
+    }
+    
+    /// Take the prompt tokens by section of the call answering `signal_id`
+    /// before `parse_response` applies them
+    pub fn take_prompt_composition(&self, signal_id: &Uuid) -> Option<PromptComposition> {
+        self.prompt_compositions.remove(signal_id).map(|(_, composition)| composition)
+    }
+    
+    /// Take the memory entries this neuron's response to `signal_id` cited,
+    /// if it cited any
+    pub fn take_citations(&self, signal_id: &Uuid) -> Option<Vec<CitedMemory>> {
+        self.citations.remove(signal_id).map(|(_, cited)| cited)
+    }
+    
+    /// Split the citation trailer off a response to `signal_id`, keeping
+    /// the entries it cited among those `injected` into its prompt
+    fn split_citations(&self, signal_id: Uuid, injected: &[CitedMemory], response: String) -> String {
+        let Some(trailer) = CitationTrailer::parse(&response) else {
+            return response;
+        };
+        let resolved = citations::resolve(&trailer.references, injected);
+        if !resolved.is_empty() {
+            let mut cited = self.citations.entry(signal_id).or_default();
+            for memory in resolved {
+                if !cited.contains(&memory) {
+                    cited.push(memory);
+                }
+            }
+        }
+        trailer.body
+    }
+    
+    /// Count the citations of the response to `signal_id` on the entries it
+    /// cited. The counts are memory writes, skipped while the server is
+    /// read-only.
+    async fn count_citations(&self, signal_id: &Uuid) {
+        let Some(memory) = &self.memory else {
+            return;
+        };
+        if self.read_only.as_ref().is_some_and(|gate| gate.is_active()) {
+            return;
+        }
+        let cited = self.citations.get(signal_id).map(|cited| cited.clone()).unwrap_or_default();
+        for entry in cited {
+            let Ok(id) = entry.memory_id.parse::<Uuid>() else {
+                continue;
+            };
+            if let Err(e) = memory.store().record_citation(id).await {
+                warn!("Failed to record citation of memory {}: {}", id, e);
+            }
+        }
+    }
+    
+    /// Process signals in a supervised worker process instead of in-process
+    pub fn set_worker(&mut self, worker: Arc<NeuronWorker>) {
+        self.worker = Some(worker);
+    }
+    
+    /// Worker process of an isolated neuron
+    pub fn worker(&self) -> Option<&Arc<NeuronWorker>> {
+        self.worker.as_ref()
+    }
+    
+    /// Output format requested for the chain `signal` belongs to, when this
+    /// neuron produces its final output
+    fn output_format(&self, signal: &NeuronSignal) -> Option<FormatRequest> {
+        if !self.is_terminal() {
+            return None;
+        }
+        match FormatRequest::from_metadata(&signal.metadata) {
+            Ok(request) => request,
+            Err(e) => {
+                warn!(
+                    target: "neuron.output_format",
+                    neuron_id = %self.id,
+                    signal_id = %signal.signal_id,
+                    "Ignoring output format: {}", e
+                );
+                None
+            }
+        }
+    }
+    
+    /// Check `response` against its requested format, handing it to the
+    /// reformatter until it conforms or retries run out. An output that never
+    /// conforms is returned as it was and reported as degraded.
+    async fn conform_output(&self, signal: &NeuronSignal, request: &FormatRequest, response: String) -> String {
+        let mut current = response.clone();
+        let mut retries = 0;
+        let mut last_failure = None;
+        loop {
+            let failure = request.check(&current).err();
+            if let Some(metrics) = &self.metrics {
+                metrics.record_validation(request.validator(), failure.is_none());
+            }
+            let reason = match failure {
+                None => {
+                    self.format_reports.insert(signal.signal_id, FormatReport {
+                        format: request.format(),
+                        degraded: false,
+                        reason: last_failure,
+                        reformat_retries: retries,
+                    });
+                    return current;
+                }
+                Some(reason) => format!("{}: {}", request.validator(), reason),
+            };
+            
+            let reformatted = match &self.reformatter {
+                Some(reformatter) if retries < self.max_reformat_retries => {
+                    retries += 1;
+                    debug!(
+                        target: "neuron.output_format",
+                        neuron_id = %self.id,
+                        retry = retries,
+                        reason = %reason,
+                        "Reformatting output"
+                    );
+                    reformatter.send_message(&request.reformat_prompt(&current, &reason)).await
+                }
+                _ => Err(Error::Processing("reformat retries exhausted".to_string())),
+            };
+            match reformatted {
+                Ok(reformatted) => {
+                    current = reformatted;
+                    last_failure = Some(reason);
+                }
+                Err(e) => {
+                    warn!(
+                        target: "neuron.output_format",
+                        neuron_id = %self.id,
+                        format = request.format().as_str(),
+                        retries = retries,
+                        reason = %reason,
+                        "Returning output in degraded format: {}", e
+                    );
+                    if let Some(metrics) = &self.metrics {
+                        metrics.record_error("format_degraded");
+                    }
+                    self.format_reports.insert(signal.signal_id, FormatReport {
+                        format: request.format(),
+                        degraded: true,
+                        reason: Some(reason),
+                        reformat_retries: retries,
+                    });
+                    return response;
+                }
+            }
+        }
+    }
+    
+    /// This neuron's private memory namespace
+    pub fn private_namespace(&self) -> MemoryNamespace {
+        MemoryNamespace::Private(self.id.clone())
+    }
+    
+    /// Write an entry to a memory namespace, subject to the memory policy and
+    /// the namespace's quota. While the server is read-only the write is held
+    /// or skipped, as `read_only.memory_writes` says.
+    pub async fn remember(&self, namespace: &MemoryNamespace, entry: MemoryEntry) -> Result<uuid::Uuid> {
+        let memory = self.memory.as_ref()
+            .ok_or_else(|| Error::InvalidState("Memory system is disabled".to_string()))?;
+        let write = MemoryWrite::new(memory.clone(), &self.id, self.layer.as_str(), namespace, entry);
+        let admission = match &self.read_only {
+            Some(gate) => gate.admit_memory_write(write),
+            None => MemoryWriteAdmission::Write(Box::new(write)),
+        };
+        match admission {
+            MemoryWriteAdmission::Write(write) => write.apply().await,
+            MemoryWriteAdmission::Held(id) => Ok(id),
+            MemoryWriteAdmission::Skipped => Err(Error::InvalidState(
+                "Memory write skipped: the server is read-only".to_string(),
+            )),
+        }
+    }
+    
+    /// Search a memory namespace, subject to the memory policy
+    pub async fn recall(&self, namespace: &MemoryNamespace, search: MemorySearch) -> Result<Vec<MemoryEntry>> {
+        let memory = self.memory.as_ref()
+            .ok_or_else(|| Error::InvalidState("Memory system is disabled".to_string()))?;
+        memory.read(&self.id, self.layer.as_str(), namespace, search).await
     }
     
     /// Enable backward propagation
@@ -242,13 +845,73 @@ impl ManagedNeuron {
     /// Start the neuron
     pub async fn start(&self) -> Result<()> {
         info!("Starting neuron {} on layer {}", self.id, self.layer.as_str());
+        if let Some(worker) = &self.worker {
+            worker.start().await?;
+        }
         *self.state.write().await = NeuronState::Running;
         Ok(())
     }
     
+    /// Process a signal in the neuron's worker, recording its outcome here
+    /// as in-process processing would
+    async fn process_in_worker(&self, worker: &NeuronWorker, signal: &NeuronSignal) -> Result<String> {
+        let start_time = std::time::Instant::now();
+        *self.state.write().await = NeuronState::Processing;
+        if let Some(metrics) = &self.metrics {
+            metrics.record_neuron_processing_start();
+        }
+        
+        let result = worker.process(signal).await.and_then(|processed| {
+            if let Some(report) = processed.format_report {
+                self.format_reports.insert(signal.signal_id, report);
+            }
+            if let Some(report) = processed.validation_report {
+                self.validation_reports.insert(signal.signal_id, report);
+            }
+            if !processed.citations.is_empty() {
+                self.citations.insert(signal.signal_id, processed.citations);
+            }
+            if let (Some(metrics), Some(usage)) = (&self.metrics, &processed.usage) {
+                metrics.record_token_usage(usage.prompt_tokens, usage.completion_tokens);
+            }
+            processed.response.map_err(|message| Error::Neuron { id: self.id.clone(), message })
+        });
+        *self.state.write().await = NeuronState::Running;
+        
+        let duration = start_time.elapsed();
+        match &result {
+            Ok(_) => {
+                let mut stats = self.stats.write().await;
+                stats.signals_processed += 1;
+                stats.last_signal = Some(Utc::now());
+                drop(stats);
+                if let Some(metrics) = &self.metrics {
+                    metrics.record_processing_time(&self.id, duration);
+                    metrics.record_latency(self.layer.as_str(), duration);
+                    metrics.record_provider_success();
+                    metrics.record_neuron_processing_end();
+                    metrics.record_signal_processed();
+                }
+            }
+            Err(e) => {
+                self.stats.write().await.errors_count += 1;
+                if let Some(metrics) = &self.metrics {
+                    metrics.record_error(&e.to_string());
+                    metrics.record_neuron_processing_end();
+                    metrics.record_signal_failed();
+                    metrics.record_failed_latency(self.layer.as_str(), duration);
+                }
+                error!("Neuron {} failed to process signal in its worker: {}", self.id, e);
+            }
+        }
+        result
+    }
+    
     /// Format a signal into a prompt for Claude
-    async fn format_prompt(&self, signal: &NeuronSignal) -> String {
-        let tool_definitions = self.tool_registry.definitions();
+    async fn format_prompt(&self, signal: &NeuronSignal) -> ComposedPrompt {
+        // Listed by name, so the same signal makes the same prompt every run
+        let mut tool_definitions = self.tool_registry.definitions();
+        tool_definitions.sort_by(|a, b| a.name.cmp(&b.name));
         let tool_info = if !tool_definitions.is_empty() {
             let tool_list = tool_definitions.iter()
                 .map(|t| format!("- {}: {}", t.name, t.description))
@@ -260,32 +923,41 @@ impl ManagedNeuron {
         };
         
         // Build memory context if available
-        let memory_context = if let Some(memory_store) = &self.memory_store {
-            match memory_store.build_context(&self.id, &signal.payload.activation.content).await {
+        let memory_context = if let Some(memory) = &self.memory {
+            match memory.store().build_context(&self.id, &signal.payload.activation.content).await {
                 Ok(context) => {
                     let mut context_str = String::new();
+                    // Each entry is tagged with the reference id it is cited by
+                    let mut injected: Vec<CitedMemory> = Vec::new();
+                    let mut tag = |entry: &MemoryEntry| {
+                        let memory = citations::injected(entry);
+                        let line = format!("- [{}] {}\n", memory.reference, entry.content);
+                        injected.push(memory);
+                        line
+                    };
                     
                     if !context.recent_tasks.is_empty() {
                         context_str.push_str("\n\nRECENT TASKS:\n");
                         for entry in context.recent_tasks.iter().take(3) {
-                            context_str.push_str(&format!("- {}\n", entry.content));
+                            context_str.push_str(&tag(entry));
                         }
                     }
                     
                     if !context.relevant_learnings.is_empty() {
                         context_str.push_str("\nRELEVANT LEARNINGS:\n");
                         for entry in context.relevant_learnings.iter() {
-                            context_str.push_str(&format!("- {}\n", entry.content));
+                            context_str.push_str(&tag(entry));
                         }
                     }
                     
                     if !context.error_patterns.is_empty() {
                         context_str.push_str("\nKNOWN ERROR PATTERNS:\n");
                         for entry in context.error_patterns.iter() {
-                            context_str.push_str(&format!("- {}\n", entry.content));
+                            context_str.push_str(&tag(entry));
                         }
                     }
                     
+                    self.injected_memories.insert(signal.signal_id, injected);
                     context_str
                 }
                 Err(e) => {
@@ -297,33 +969,42 @@ impl ManagedNeuron {
             String::new()
         };
         
-        match signal.propagation_type {
+        let mut prompt = ComposedPrompt::new();
+        let directive = match signal.propagation_type {
             PropagationType::Forward => {
-                format!(
-                    "FORWARD_SIGNAL\nFrom: {}\nLayer: {}\nStrength: {}\nContent: {}\nFeatures: {:?}\n{}{}\nYour response should include FORWARD_TO: <target_neurons> and CONTENT: <your_analysis>",
+                prompt.push(PromptSection::LayerInstructions, "FORWARD_SIGNAL\n");
+                prompt.push(PromptSection::UserContent, format!(
+                    "From: {}\nLayer: {}\nStrength: {}\nContent: {}\nFeatures: {:?}\n",
                     signal.from_neuron,
                     signal.layer_from,
                     signal.payload.activation.strength,
                     signal.payload.activation.content,
                     signal.payload.activation.features,
-                    memory_context,
-                    tool_info
-                )
+                ));
+                "FORWARD_TO: <target_neurons> and CONTENT: <your_analysis>"
             }
             PropagationType::Backward => {
                 let gradient = signal.payload.gradient.as_ref().unwrap();
-                format!(
-                    "BACKWARD_SIGNAL\nFrom: {}\nError: {}\nMagnitude: {}\nLoss: {}\nAdjustments: {:?}\n{}{}\nYour response should include BACKWARD_TO: <target_neurons> and ERROR_TYPE: <error_description>",
-                    signal.from_neuron,
+                prompt.push(PromptSection::LayerInstructions, "BACKWARD_SIGNAL\n");
+                prompt.push(PromptSection::UserContent, format!("From: {}\n", signal.from_neuron));
+                prompt.push(PromptSection::Corrections, format!(
+                    "Error: {}\nMagnitude: {}\nLoss: {}\nAdjustments: {:?}\n",
                     gradient.error_type,
                     gradient.magnitude,
                     gradient.loss,
                     gradient.adjustments,
-                    memory_context,
-                    tool_info
-                )
+                ));
+                "BACKWARD_TO: <target_neurons> and ERROR_TYPE: <error_description>"
             }
+        };
+        // The conversation a session's chain continues, on its root signal
+        if let Some(conversation) = signal.metadata.get(SESSION_CONTEXT_KEY) {
+            prompt.push(PromptSection::Memory, format!("\n\nCONVERSATION SO FAR:\n{}\n", conversation));
         }
+        prompt.push(PromptSection::Memory, memory_context);
+        prompt.push(PromptSection::LayerInstructions, tool_info);
+        prompt.push(PromptSection::LayerInstructions, format!("\nYour response should include {}", directive));
+        prompt
     }
     
     /// Process backward propagation signal
@@ -366,7 +1047,7 @@ impl ManagedNeuron {
                     info!("Error pattern identified: {}", pattern.error_signature);
                     
                     // Store learning in memory
-                    if let Some(memory_store) = &self.memory_store {
+                    if self.memory.is_some() {
                         let learning_memory = MemoryBuilder::new(
                             self.id.clone(), 
                             self.layer.as_str().to_string()
@@ -380,7 +1061,7 @@ impl ManagedNeuron {
                         .with_importance(0.8)
                         .build();
                         
-                        let _ = memory_store.store(learning_memory).await;
+                        let _ = self.remember(&self.private_namespace(), learning_memory).await;
                     }
                 }
                 
@@ -398,37 +1079,37 @@ impl ManagedNeuron {
         Ok(())
     }
 
+    /// Signals to the targets of `instruction` this neuron connects to
+    pub fn forward_signals(&self, instruction: &ForwardInstruction) -> Vec<NeuronSignal> {
+        let mut targets: Vec<&str> = instruction.targets.iter()
+            .map(String::as_str)
+            .filter(|target| self.config.forward_connections.iter().any(|c| c == *target))
+            .collect();
+        // Naming no neuron, or the routing group, leaves the choice to the
+        // router; the group gets one signal however often it is named
+        if let Some(routing) = &self.routing {
+            if instruction.targets.is_empty() || instruction.targets.iter().any(|target| routing.routes(target)) {
+                targets.push(routing.address());
+            }
+        }
+        targets.into_iter()
+            .map(|target| NeuronSignal::forward(
+                &self.id,
+                target,
+                self.layer.as_str(),
+                &self.get_target_layer(target),
+                instruction.content.clone(),
+            ))
+            .collect()
+    }
+    
     /// Parse response and determine next signals
-    pub fn parse_response(&self, response: &str, _original_signal: &NeuronSignal) -> Vec<NeuronSignal> {
+    pub fn parse_response(&self, response: &str, original_signal: &NeuronSignal) -> Vec<NeuronSignal> {
         let mut signals = Vec::new();
         
         // Parse FORWARD_TO directive
-        if let Some(forward_line) = response.lines().find(|l| l.starts_with("FORWARD_TO:")) {
-            let targets = forward_line
-                .strip_prefix("FORWARD_TO:")
-                .unwrap_or("")
-                .split(',')
-                .map(|s| s.trim())
-                .filter(|s| !s.is_empty());
-                
-            // Extract content after CONTENT: line
-            let content = response.lines()
-                .skip_while(|l| !l.starts_with("CONTENT:"))
-                .skip(1)
-                .collect::<Vec<_>>()
-                .join("\n");
-                
-            for target in targets {
-                if self.config.forward_connections.contains(&target.to_string()) {
-                    signals.push(NeuronSignal::forward(
-                        &self.id,
-                        target,
-                        self.layer.as_str(),
-                        &self.get_target_layer(target),
-                        content.clone(),
-                    ));
-                }
-            }
+        if let Some(instruction) = ForwardInstruction::parse(response) {
+            signals.extend(self.forward_signals(&instruction));
         }
         
         // Parse BACKWARD_TO directive for error propagation
@@ -449,17 +1130,78 @@ impl ManagedNeuron {
                 
             for target in targets {
                 if self.config.backward_connections.contains(&target.to_string()) {
-                    signals.push(NeuronSignal::backward(
+                    let mut signal = NeuronSignal::backward(
                         &self.id,
                         target,
                         self.layer.as_str(),
                         &self.get_target_layer(target),
                         Gradient::new(error_type.clone(), 0.5),
-                    ));
+                    );
+                    mark_gradient(&mut signal, &error_type, 0.5);
+                    signals.push(signal);
                 }
             }
         }
         
+        // Keep within the chain's branch and node limits
+        if let Some(truncation) = self.fan_out.apply(original_signal, self.layer.as_str(), &mut signals) {
+            self.fan_out_truncations.insert(original_signal.signal_id, truncation);
+        }
+        
+        // Record how the response fared against its validators
+        if let Some((_, report)) = self.validation_reports.remove(&original_signal.signal_id) {
+            for signal in &mut signals {
+                report.apply_to(&mut signal.metadata);
+            }
+        }
+        
+        // And the timeout its call was given
+        if let Some(decision) = self.take_timeout_decision(&original_signal.signal_id) {
+            for signal in &mut signals {
+                decision.apply_to(&mut signal.metadata);
+            }
+        }
+        
+        // And the memory entries it cited
+        if let Some(cited) = self.citations.get(&original_signal.signal_id) {
+            for signal in &mut signals {
+                citations::apply_to(&mut signal.metadata, &cited);
+            }
+        }
+        
+        // And what its prompt spent tokens on
+        if let Some(composition) = self.take_prompt_composition(&original_signal.signal_id) {
+            for signal in &mut signals {
+                composition.apply_to(&mut signal.metadata);
+            }
+        }
+        
+        inherit_metadata(&mut signals, original_signal);
+        signals
+    }
+    
+    /// Forward `tasks` without calling the provider, assigning them to the
+    /// neuron's forward connections in turn. Used in place of a response
+    /// while degraded; fan-out limits apply as they would to one.
+    pub fn decompose(&self, tasks: &[String], original_signal: &NeuronSignal) -> Vec<NeuronSignal> {
+        let targets = &self.config.forward_connections;
+        if targets.is_empty() {
+            return Vec::new();
+        }
+        let mut signals: Vec<_> = tasks.iter()
+            .zip(targets.iter().cycle())
+            .map(|(task, target)| NeuronSignal::forward(
+                &self.id,
+                target,
+                self.layer.as_str(),
+                &self.get_target_layer(target),
+                task.clone(),
+            ))
+            .collect();
+        if let Some(truncation) = self.fan_out.apply(original_signal, self.layer.as_str(), &mut signals) {
+            self.fan_out_truncations.insert(original_signal.signal_id, truncation);
+        }
+        inherit_metadata(&mut signals, original_signal);
         signals
     }
     
@@ -480,6 +1222,25 @@ impl ManagedNeuron {
     }
 }
 
+/// Child signals inherit the parent's metadata (chain ID, tags, ...),
+/// except the entries it cited, which are its own, the session
+/// conversation only the root's prompt carries, and the parent's route
+fn inherit_metadata(signals: &mut [NeuronSignal], original_signal: &NeuronSignal) {
+    for signal in signals {
+        signal.metadata.insert(PARENT_ID_KEY.to_string(), original_signal.signal_id.to_string());
+        for (key, value) in &original_signal.metadata {
+            // Citations and prompt tokens describe the response they came
+            // with, and a route the forward it was chosen for
+            if [citations::CITED_KEY, PROMPT_SECTIONS_KEY, PROMPT_TOKENIZER_KEY, SESSION_CONTEXT_KEY].contains(&key.as_str())
+                || ROUTE_KEYS.contains(&key.as_str())
+            {
+                continue;
+            }
+            signal.metadata.entry(key.clone()).or_insert_with(|| value.clone());
+        }
+    }
+}
+
 #[async_trait]
 impl NeuronInterface for ManagedNeuron {
     fn id(&self) -> &str {
@@ -504,6 +1265,11 @@ impl NeuronInterface for ManagedNeuron {
             "Processing signal"
         );
         
+        // Isolated neurons do all of their processing in the worker
+        if let Some(worker) = &self.worker {
+            return self.process_in_worker(worker, signal).await;
+        }
+        
         // Handle backward propagation signals
         if signal.propagation_type == PropagationType::Backward {
             self.process_backward_signal(signal).await?;
@@ -518,6 +1284,12 @@ impl NeuronInterface for ManagedNeuron {
             return Ok("Backward propagation processed".to_string());
         }
         
+        // Injected faults hit before the cache can answer, and again on
+        // every recovery retry
+        if let Some(chaos) = &self.chaos {
+            chaos.before_processing(&self.id, self.layer.as_str()).await?;
+        }
+        
         // Check circuit breaker first
         if !self.circuit_breaker.allow_request().await {
             warn!(
@@ -550,15 +1322,12 @@ impl NeuronInterface for ManagedNeuron {
             metrics.record_neuron_processing_start();
         }
         
-        // Get base prompt (potentially adjusted by learning)
-        let base_prompt = if let Some(adjuster) = &self.prompt_adjuster {
-            adjuster.read().await.get_current_prompt().to_string()
-        } else {
-            self.format_prompt(signal).await
-        };
-        
-        // Format prompt with signal context
-        let prompt = format!("{}\n\n{}", base_prompt, self.format_prompt(signal).await);
+        let output_format = self.output_format(signal);
+        let composed = self.prompt_for(signal, output_format.as_ref()).await;
+        let prompt = composed.text();
+        let injected = self.injected_memories.remove(&signal.signal_id)
+            .map(|(_, injected)| injected)
+            .unwrap_or_default();
         let _cache_key = format!("{}-{}", self.layer.as_str(), prompt.len());
         
         // Check cache first (for all layers, not just L2)
@@ -599,7 +1368,10 @@ impl NeuronInterface for ManagedNeuron {
                     "layer" => self.layer.as_str(),
                     "cache_hit" => true
                 );
-                return Ok(cached_response);
+                return Ok(match &output_format {
+                    Some(request) => self.conform_output(signal, request, cached_response).await,
+                    None => cached_response,
+                });
             }
         }
         
@@ -627,13 +1399,27 @@ impl NeuronInterface for ManagedNeuron {
                 break;
             }
             
-            // Send to Claude with timeout to prevent hanging
-            let timeout_duration = std::time::Duration::from_secs(30);
-            let response = match tokio::time::timeout(
-                timeout_duration,
-                self.claude.send_message(&current_prompt)
+            // Send to Claude with timeout to prevent hanging. The provider
+            // sees the signal's deadline, so batching ones can skip the wait.
+            let decision = self.timeouts.decide(&self.id, self.layer.as_str());
+            self.timeout_decisions.insert(signal.signal_id, decision);
+            let timeout_duration = decision.duration();
+            let call_start = tokio::time::Instant::now();
+            let deadline = local_model::signal_deadline(signal, timeout_duration);
+            let call = async {
+                match self.stream_sinks.remove(&signal.signal_id) {
+                    Some((_, partial)) => self.claude.stream_message(&current_prompt, &partial).await,
+                    None => self.claude.send_message(&current_prompt).await,
+                }
+            };
+            let response = match tokio::time::timeout_at(
+                deadline,
+                local_model::with_deadline(deadline, call)
             ).await {
-                Ok(Ok(resp)) => resp,
+                Ok(Ok(resp)) => {
+                    self.timeouts.record(&self.id, self.layer.as_str(), call_start.elapsed());
+                    resp
+                }
                 Ok(Err(e)) => {
                     // Update error stats
                     let mut stats = self.stats.write().await;
@@ -644,6 +1430,7 @@ impl NeuronInterface for ManagedNeuron {
                     if let Some(metrics) = &self.metrics {
                         metrics.record_error(&e.to_string());
                         metrics.record_signal_failed();
+                        metrics.record_failed_latency(self.layer.as_str(), start_time.elapsed());
                     }
                     
                     // Record failure with circuit breaker
@@ -653,8 +1440,21 @@ impl NeuronInterface for ManagedNeuron {
                     return Err(e);
                 }
                 Err(_) => {
-                    // Timeout error
-                    let timeout_err = Error::Network("Claude API timeout".to_string());
+                    // The signal's own deadline, when it came first, says
+                    // nothing about this neuron's latency
+                    let (timeout_err, kind) = if deadline < call_start + timeout_duration {
+                        let deadline = signal.metadata.get(keys::routing::DEADLINE).cloned().unwrap_or_default();
+                        (Error::DeadlineExceeded { neuron: self.id.clone(), deadline }, "deadline_exceeded")
+                    } else {
+                        self.timeouts.record(&self.id, self.layer.as_str(), timeout_duration);
+                        match decision.source {
+                            TimeoutSource::Adaptive => (
+                                Error::AdaptiveTimeout { neuron: self.id.clone(), timeout_ms: decision.timeout_ms },
+                                "adaptive_timeout",
+                            ),
+                            _ => (Error::Network("Claude API timeout".to_string()), "timeout"),
+                        }
+                    };
                     
                     // Update error stats
                     let mut stats = self.stats.write().await;
@@ -663,8 +1463,9 @@ impl NeuronInterface for ManagedNeuron {
                     
                     // Record error metrics
                     if let Some(metrics) = &self.metrics {
-                        metrics.record_error("timeout");
+                        metrics.record_error(kind);
                         metrics.record_signal_failed();
+                        metrics.record_failed_latency(self.layer.as_str(), start_time.elapsed());
                     }
                     
                     // Record failure with circuit breaker
@@ -674,7 +1475,8 @@ impl NeuronInterface for ManagedNeuron {
                         target: "neuron.timeout",
                         neuron_id = %self.id,
                         timeout_ms = timeout_duration.as_millis(),
-                        "Request timed out"
+                        source = decision.source.as_str(),
+                        "Request timed out: {}", timeout_err
                     );
                     let duration = perf_timer.elapsed();
                     log_performance!(
@@ -683,7 +1485,7 @@ impl NeuronInterface for ManagedNeuron {
                         false,
                         "neuron_id" => &self.id,
                         "layer" => self.layer.as_str(),
-                        "error" => "timeout"
+                        "error" => kind
                     );
                     return Err(timeout_err);
                 }
@@ -692,10 +1494,22 @@ impl NeuronInterface for ManagedNeuron {
             // Early success recording for circuit breaker
             self.circuit_breaker.record_success().await;
             
+            // The first call is the one sent the composed prompt; tool
+            // follow-ups add their results to it
+            if iterations == 1 {
+                if let Some(accounting) = &self.prompt_accounting {
+                    let usage = self.claude.last_token_usage();
+                    let system_prompt = self.claude.system_prompt();
+                    if let Some(composition) = accounting.record(&self.id, self.layer.as_str(), &composed, system_prompt, usage.as_ref()) {
+                        self.prompt_compositions.insert(signal.signal_id, composition);
+                    }
+                }
+            }
+            
             // Check if response contains tool requests
             if let Some(tool_line) = response.lines().find(|l| l.starts_with("TOOL:")) {
                 // Parse tool request
-                let parts: Vec<&str> = tool_line.strip_prefix("TOOL:").unwrap_or("").splitn(2, ' ').collect();
+                let parts: Vec<&str> = tool_line.strip_prefix("TOOL:").unwrap_or("").trim_start().splitn(2, ' ').collect();
                 if parts.len() == 2 {
                     let tool_name = parts[0].trim();
                     let params_str = parts[1].trim();
@@ -706,7 +1520,7 @@ impl NeuronInterface for ManagedNeuron {
                     match serde_json::from_str::<Value>(params_str) {
                         Ok(params) => {
                             // Execute tool
-                            match self.tool_registry.execute(tool_name, params).await {
+                            match self.execute_tool(signal, tool_name, params).await {
                                 Ok(result) => {
                                     // Add tool result to response
                                     full_response.push_str(&response);
@@ -747,6 +1561,34 @@ impl NeuronInterface for ManagedNeuron {
             }
         }
         
+        // The citation trailer is not part of the output
+        let full_response = self.split_citations(signal.signal_id, &injected, full_response);
+        
+        // Reject or correct the response before anything is cached or forwarded
+        let full_response = match self.validate_response(signal, &current_prompt, full_response).await {
+            Ok(response) => self.split_citations(signal.signal_id, &injected, response),
+            Err(e) => {
+                self.citations.remove(&signal.signal_id);
+                self.stats.write().await.errors_count += 1;
+                if let Some(metrics) = &self.metrics {
+                    metrics.record_error(match e {
+                        Error::Processing(_) => "validation_failed",
+                        _ => "validation_retry_failed",
+                    });
+                    metrics.record_neuron_processing_end();
+                    metrics.record_signal_failed();
+                    metrics.record_failed_latency(self.layer.as_str(), start_time.elapsed());
+                }
+                *self.state.write().await = NeuronState::Running;
+                return Err(e);
+            }
+        };
+        
+        let full_response = match &output_format {
+            Some(request) => self.conform_output(signal, request, full_response).await,
+            None => full_response,
+        };
+        
         // Update stats after all iterations
         let mut stats = self.stats.write().await;
         stats.signals_processed += 1;
@@ -758,6 +1600,7 @@ impl NeuronInterface for ManagedNeuron {
             let duration = start_time.elapsed();
             metrics.record_processing_time(&self.id, duration);
             metrics.record_latency(self.layer.as_str(), duration);
+            metrics.record_provider_success();
             
             // Record token usage if available
             if let Some(usage) = self.claude.last_token_usage() {
@@ -767,6 +1610,7 @@ impl NeuronInterface for ManagedNeuron {
         
         // Record success with circuit breaker
         self.circuit_breaker.record_success().await;
+        self.count_citations(&signal.signal_id).await;
         
         // Cache the final response for L2 neurons
         if let Some(cache) = &self.response_cache {
@@ -774,7 +1618,9 @@ impl NeuronInterface for ManagedNeuron {
         }
         
         // Store memory of this interaction
-        if let Some(memory_store) = &self.memory_store {
+        if self.memory.is_some() {
+            let namespace = self.private_namespace();
+            
             // Store the task
             let task_memory = MemoryBuilder::new(self.id.clone(), self.layer.as_str().to_string())
                 .with_type(MemoryType::Task)
@@ -787,7 +1633,7 @@ impl NeuronInterface for ManagedNeuron {
                 .with_importance(0.7)
                 .build();
                 
-            if let Err(e) = memory_store.store(task_memory).await {
+            if let Err(e) = self.remember(&namespace, task_memory).await {
                 warn!("Failed to store task memory: {}", e);
             }
             
@@ -802,7 +1648,7 @@ impl NeuronInterface for ManagedNeuron {
                 .with_importance(0.6)
                 .build();
                 
-            if let Err(e) = memory_store.store(result_memory).await {
+            if let Err(e) = self.remember(&namespace, result_memory).await {
                 warn!("Failed to store result memory: {}", e);
             }
         }
@@ -841,7 +1687,11 @@ impl NeuronInterface for ManagedNeuron {
     }
     
     async fn health(&self) -> Result<NeuronHealth> {
-        let state = *self.state.read().await;
+        let mut state = *self.state.read().await;
+        // Down between a worker crash and its restart
+        if state != NeuronState::Stopped && self.worker.as_ref().is_some_and(|worker| !worker.is_up()) {
+            state = NeuronState::Failed;
+        }
         let stats = self.stats.read().await;
         
         let uptime_seconds = stats.started_at
@@ -860,6 +1710,9 @@ impl NeuronInterface for ManagedNeuron {
     async fn shutdown(&self) -> Result<()> {
         info!("Shutting down neuron {}", self.id);
         *self.state.write().await = NeuronState::Stopped;
+        if let Some(worker) = &self.worker {
+            worker.shutdown().await;
+        }
         Ok(())
     }
 }
@@ -870,6 +1723,9 @@ pub struct NeuronRegistry {
     neurons: Arc<DashMap<String, Arc<ManagedNeuron>>>,
     metrics: Option<Arc<crate::metrics::Metrics>>,
     parallel_executor: crate::performance::ParallelExecutor,
+    dead_letters: Arc<DeadLetterQueue>,
+    warm_pool: Arc<WarmPool>,
+    changes: ChangeFeed,
 }
 
 impl Default for NeuronRegistry {
@@ -885,11 +1741,36 @@ impl NeuronRegistry {
             neurons: Arc::new(DashMap::new()),
             metrics: None,
             parallel_executor: crate::performance::ParallelExecutor::new(10), // 10 concurrent operations
+            dead_letters: Arc::new(DeadLetterQueue::default()),
+            warm_pool: Arc::new(WarmPool::new(Default::default())),
+            changes: ChangeFeed::new(),
         }
     }
     
+    /// Versioned record of neuron changes, see [`crate::neuron_changes`]
+    pub fn changes(&self) -> &ChangeFeed {
+        &self.changes
+    }
+    
+    /// Signals rejected by full neuron queues
+    pub fn dead_letters(&self) -> &Arc<DeadLetterQueue> {
+        &self.dead_letters
+    }
+    
+    /// Pre-initialized instances of neuron types with a slow cold start
+    pub fn warm_pool(&self) -> &Arc<WarmPool> {
+        &self.warm_pool
+    }
+    
+    /// An instance of `neuron_type` from its warm pool, instantiated on
+    /// demand when the pool is empty
+    pub async fn checkout(&self, neuron_type: &str) -> Result<PooledInstance> {
+        self.warm_pool.checkout(neuron_type).await
+    }
+    
     /// Set metrics collector
     pub fn set_metrics(&mut self, metrics: Arc<crate::metrics::Metrics>) {
+        self.warm_pool.set_metrics(metrics.clone());
         self.metrics = Some(metrics);
     }
     
@@ -975,15 +1856,7 @@ impl NeuronRegistry {
         let mut infos = Vec::new();
         
         for neuron in self.all() {
-            let state = *neuron.state.read().await;
-            let health = neuron.health().await.ok();
-            
-            infos.push(crate::server::NeuronInfo {
-                id: neuron.id.clone(),
-                layer: neuron.layer.as_str().to_string(),
-                state: format!("{:?}", state),
-                is_healthy: health.map(|h| h.errors_count == 0).unwrap_or(false),
-            });
+            infos.push(Self::info(&neuron).await);
         }
         
         infos
@@ -991,18 +1864,40 @@ impl NeuronRegistry {
     
     /// Get specific neuron info
     pub async fn get_info(&self, id: &str) -> Option<crate::server::NeuronInfo> {
-        if let Some(neuron) = self.get(id) {
-            let state = *neuron.state.read().await;
-            let health = neuron.health().await.ok();
-            
-            Some(crate::server::NeuronInfo {
-                id: neuron.id.clone(),
-                layer: neuron.layer.as_str().to_string(),
-                state: format!("{:?}", state),
-                is_healthy: health.map(|h| h.errors_count == 0).unwrap_or(false),
-            })
-        } else {
-            None
+        match self.get(id) {
+            Some(neuron) => Some(Self::info(&neuron).await),
+            None => None,
         }
     }
+    
+    async fn info(neuron: &ManagedNeuron) -> crate::server::NeuronInfo {
+        let state = *neuron.state.read().await;
+        let health = neuron.health().await.ok();
+        
+        crate::server::NeuronInfo {
+            id: neuron.id.clone(),
+            layer: neuron.layer.as_str().to_string(),
+            state: format!("{:?}", state),
+            is_healthy: health.map(|h| h.errors_count == 0).unwrap_or(false),
+            concurrency: neuron.concurrency.stats(),
+            circuit_breaker: neuron.circuit_state().await.as_str().to_string(),
+        }
+    }
+    
+    /// Snapshot every neuron into the change feed and return its version
+    pub async fn refresh_changes(&self) -> u64 {
+        let _observing = self.changes.lock().await;
+        let neurons = self.all();
+        let mut infos = Vec::with_capacity(neurons.len());
+        for neuron in &neurons {
+            infos.push((Self::info(neuron).await, &neuron.config));
+        }
+        self.changes.observe(infos)
+    }
+    
+    /// Neurons that changed after version `since`, as of now
+    pub async fn changes_since(&self, since: Option<u64>) -> NeuronChanges {
+        self.refresh_changes().await;
+        self.changes.since(since)
+    }
 }
\ No newline at end of file
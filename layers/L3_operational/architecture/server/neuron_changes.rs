@@ -0,0 +1,218 @@
+//! Neuron change feed
+//!
+//! Dashboards poll the neuron list, and most polls find nothing changed.
+//! The registry keeps a version that is bumped whenever a neuron's state,
+//! health, queue depth, circuit breaker or config changes, so a client
+//! passes the version it last saw and gets only the neurons that changed
+//! since. Queue depth counts in [`QueueDepth`] buckets, so the version does
+//! not churn with every signal.
+//!
+//! Changes are found by comparing snapshots of the registry, taken on
+//! every request for changes and every [`REFRESH_INTERVAL`] for streaming
+//! clients.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use parking_lot::Mutex;
+use serde_json::Value;
+use tokio::sync::watch;
+
+use hal9_core::NeuronConfig;
+
+use crate::server::NeuronInfo;
+
+pub use hal9_api_types::NeuronChanges;
+
+/// How often streaming clients are sent what changed
+pub const REFRESH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Removed neurons remembered; clients asking for changes from before the
+/// oldest forgotten removal get the full list
+pub const MAX_REMOVALS: usize = 1024;
+
+/// Bucket of the signals a neuron has admitted, processing or queued
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum QueueDepth {
+    /// None
+    Empty,
+    /// 1 to 10
+    Low,
+    /// 11 to 100
+    Medium,
+    /// Over 100
+    High,
+}
+
+impl QueueDepth {
+    pub fn of(depth: usize) -> Self {
+        match depth {
+            0 => QueueDepth::Empty,
+            1..=10 => QueueDepth::Low,
+            11..=100 => QueueDepth::Medium,
+            _ => QueueDepth::High,
+        }
+    }
+}
+
+/// What a neuron is compared on between snapshots
+#[derive(Debug, Clone, PartialEq)]
+struct Fingerprint {
+    state: String,
+    is_healthy: bool,
+    queue_depth: QueueDepth,
+    circuit_breaker: String,
+    config: Value,
+}
+
+impl Fingerprint {
+    fn of(info: &NeuronInfo, config: &NeuronConfig) -> Self {
+        Self {
+            state: info.state.clone(),
+            is_healthy: info.is_healthy,
+            queue_depth: QueueDepth::of(info.concurrency.in_flight + info.concurrency.queued),
+            circuit_breaker: info.circuit_breaker.clone(),
+            config: serde_json::to_value(config).unwrap_or_default(),
+        }
+    }
+}
+
+struct Entry {
+    fingerprint: Fingerprint,
+    info: NeuronInfo,
+    /// Version the neuron last changed in
+    version: u64,
+}
+
+#[derive(Default)]
+struct Feed {
+    version: u64,
+    entries: HashMap<String, Entry>,
+    /// Version each neuron was removed in
+    removals: HashMap<String, u64>,
+    /// Latest version of a forgotten removal
+    forgotten: u64,
+}
+
+/// Versioned record of the neurons' last observed state
+pub struct ChangeFeed {
+    feed: Mutex<Feed>,
+    versions: watch::Sender<u64>,
+    /// Serialises observations, so an older snapshot never lands after a
+    /// newer one
+    observing: tokio::sync::Mutex<()>,
+}
+
+impl Default for ChangeFeed {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ChangeFeed {
+    pub fn new() -> Self {
+        Self {
+            feed: Mutex::new(Feed::default()),
+            versions: watch::channel(0).0,
+            observing: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    /// Held while a snapshot is taken and observed
+    pub async fn lock(&self) -> tokio::sync::MutexGuard<'_, ()> {
+        self.observing.lock().await
+    }
+
+    /// Record a snapshot of every neuron with its config. Neurons that
+    /// changed, appeared or are missing since the last snapshot take a new
+    /// version. Returns the current version.
+    pub fn observe(&self, neurons: Vec<(NeuronInfo, &NeuronConfig)>) -> u64 {
+        let mut feed = self.feed.lock();
+        let next = feed.version + 1;
+        let mut changed = false;
+
+        let mut seen = std::collections::HashSet::new();
+        for (info, config) in neurons {
+            let fingerprint = Fingerprint::of(&info, config);
+            seen.insert(info.id.clone());
+            match feed.entries.get_mut(&info.id) {
+                Some(entry) if entry.fingerprint == fingerprint => entry.info = info,
+                Some(entry) => {
+                    *entry = Entry { fingerprint, info, version: next };
+                    changed = true;
+                }
+                None => {
+                    feed.removals.remove(&info.id);
+                    feed.entries.insert(info.id.clone(), Entry { fingerprint, info, version: next });
+                    changed = true;
+                }
+            }
+        }
+
+        let gone: Vec<String> = feed.entries.keys().filter(|id| !seen.contains(*id)).cloned().collect();
+        for id in gone {
+            feed.entries.remove(&id);
+            feed.removals.insert(id, next);
+            changed = true;
+        }
+        while feed.removals.len() > MAX_REMOVALS {
+            let (id, version) = feed.removals.iter()
+                .min_by_key(|(_, version)| **version)
+                .map(|(id, version)| (id.clone(), *version))
+                .expect("removals are not empty");
+            feed.removals.remove(&id);
+            feed.forgotten = feed.forgotten.max(version);
+        }
+
+        if changed {
+            feed.version = next;
+            self.versions.send_replace(next);
+        }
+        feed.version
+    }
+
+    /// Neurons that changed after version `since`, as of the last snapshot.
+    /// Without a version, or with one this feed cannot answer for, every
+    /// neuron is listed.
+    pub fn since(&self, since: Option<u64>) -> NeuronChanges {
+        let feed = self.feed.lock();
+        let since = since.filter(|since| *since <= feed.version && *since >= feed.forgotten);
+        let mut changes = NeuronChanges {
+            version: feed.version,
+            full: since.is_none(),
+            changed: feed.entries.values()
+                .filter(|entry| since.is_none_or(|since| entry.version > since))
+                .map(|entry| entry.info.clone())
+                .collect(),
+            removed: match since {
+                Some(since) => feed.removals.iter()
+                    .filter(|(_, version)| **version > since)
+                    .map(|(id, _)| id.clone())
+                    .collect(),
+                None => Vec::new(),
+            },
+        };
+        changes.changed.sort_by(|a, b| a.id.cmp(&b.id));
+        changes.removed.sort();
+        changes
+    }
+
+    pub fn version(&self) -> u64 {
+        self.feed.lock().version
+    }
+
+    /// Receives every new version
+    pub fn subscribe(&self) -> watch::Receiver<u64> {
+        self.versions.subscribe()
+    }
+}
+
+/// Snapshot the registry for streaming clients every [`REFRESH_INTERVAL`]
+pub async fn refresh_task(registry: std::sync::Arc<crate::neuron::NeuronRegistry>) {
+    let mut interval = tokio::time::interval(REFRESH_INTERVAL);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+    loop {
+        interval.tick().await;
+        registry.refresh_changes().await;
+    }
+}
@@ -0,0 +1,648 @@
+//! Organization usage reports for enterprise dashboards
+//!
+//! Chains, webhook deliveries and dead-lettered signals that belong to an
+//! organization are recorded in the auth database as they happen: chains
+//! when their first step is recorded and again when they finish, with one
+//! row per step for tokens and cost. A report for a day, week or month is a
+//! set of SQL aggregates over those rows, cached for [`REPORT_CACHE_TTL`] so
+//! dashboards polling the endpoint do not re-run them on every request.
+//!
+//! Rows are attributed to a period by when the chain, delivery or dead
+//! letter was created, in UTC.
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, FixedOffset, Utc};
+use dashmap::DashMap;
+use serde::Serialize;
+use sqlx::Row;
+use tokio::sync::broadcast;
+use tracing::error;
+
+use hal9_core::{config::BudgetPeriod, sqlite::SqlitePools, Error, Result};
+
+use crate::{
+    budget_period::{next_reset, period_start},
+    chain_compare::step_cost,
+    chain_tracker::{ChainRecord, ChainResult, ChainStatus, ChainTracker, ORG_ID_KEY},
+    concurrency::DeadLetter,
+    webhooks::{DeliveryStatus, FinishedDelivery},
+};
+
+/// How long a report is served from cache
+pub const REPORT_CACHE_TTL: Duration = Duration::from_secs(30);
+
+/// Users listed in a report's top spenders
+pub const TOP_USERS: i64 = 10;
+
+/// Tokens and cost of one layer or model
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct UsageBreakdown {
+    /// Layer or model name
+    pub name: String,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub cost_usd: f64,
+}
+
+/// Spend of one user
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct UserSpend {
+    pub user_id: String,
+    pub chains: u64,
+    pub tokens: u64,
+    pub cost_usd: f64,
+}
+
+/// Change of the headline numbers since the previous period
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct UsageDeltas {
+    pub chains_started: i64,
+    pub chains_completed: i64,
+    pub chains_failed: i64,
+    pub tokens: i64,
+    pub cost_usd: f64,
+    /// Absent unless both periods had deliveries
+    pub webhook_success_rate: Option<f64>,
+    pub dead_letters: i64,
+    /// Absent unless both periods had finished chains
+    pub avg_chain_latency_ms: Option<f64>,
+}
+
+/// The previous period and how this one differs from it
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct UsageComparison {
+    pub period_start: DateTime<Utc>,
+    pub period_end: DateTime<Utc>,
+    pub deltas: UsageDeltas,
+}
+
+/// Usage of one organization over one period
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct OrgUsage {
+    pub org_id: String,
+    pub period: BudgetPeriod,
+    pub period_start: DateTime<Utc>,
+    pub period_end: DateTime<Utc>,
+    pub chains_started: u64,
+    pub chains_completed: u64,
+    pub chains_failed: u64,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub cost_usd: f64,
+    pub by_layer: Vec<UsageBreakdown>,
+    pub by_model: Vec<UsageBreakdown>,
+    /// Highest spenders first, at most [`TOP_USERS`]
+    pub top_users: Vec<UserSpend>,
+    /// Event deliveries that were delivered or dead-lettered
+    pub webhook_deliveries: u64,
+    /// Share of `webhook_deliveries` delivered; absent without deliveries
+    pub webhook_success_rate: Option<f64>,
+    pub dead_letters: u64,
+    /// Mean duration of completed and failed chains; absent without any
+    pub avg_chain_latency_ms: Option<f64>,
+    /// Present when asked to compare to the previous period
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub comparison: Option<UsageComparison>,
+}
+
+impl OrgUsage {
+    fn tokens(&self) -> u64 {
+        self.prompt_tokens + self.completion_tokens
+    }
+
+    fn deltas_since(&self, previous: &OrgUsage) -> UsageDeltas {
+        let delta = |now: u64, before: u64| now as i64 - before as i64;
+        let delta_of = |now: Option<f64>, before: Option<f64>| now.zip(before).map(|(now, before)| now - before);
+        UsageDeltas {
+            chains_started: delta(self.chains_started, previous.chains_started),
+            chains_completed: delta(self.chains_completed, previous.chains_completed),
+            chains_failed: delta(self.chains_failed, previous.chains_failed),
+            tokens: delta(self.tokens(), previous.tokens()),
+            cost_usd: self.cost_usd - previous.cost_usd,
+            webhook_success_rate: delta_of(self.webhook_success_rate, previous.webhook_success_rate),
+            dead_letters: delta(self.dead_letters, previous.dead_letters),
+            avg_chain_latency_ms: delta_of(self.avg_chain_latency_ms, previous.avg_chain_latency_ms),
+        }
+    }
+}
+
+/// Parse a `period` query value: `day`, `week` or `month`
+pub fn parse_period(period: &str) -> Result<BudgetPeriod> {
+    match period {
+        "day" | "daily" => Ok(BudgetPeriod::Daily),
+        "week" | "weekly" => Ok(BudgetPeriod::Weekly),
+        "month" | "monthly" => Ok(BudgetPeriod::Monthly),
+        other => Err(Error::InvalidInput(format!("Unknown period '{}': expected day, week or month", other))),
+    }
+}
+
+fn utc() -> FixedOffset {
+    FixedOffset::east_opt(0).unwrap()
+}
+
+fn millis(at: DateTime<Utc>) -> i64 {
+    at.timestamp_millis()
+}
+
+fn status_name(status: ChainStatus) -> &'static str {
+    match status {
+        ChainStatus::Running => "running",
+        ChainStatus::Completed => "completed",
+        ChainStatus::Failed => "failed",
+        ChainStatus::Cancelled => "cancelled",
+    }
+}
+
+fn storage_error(e: sqlx::Error) -> Error {
+    Error::Storage(format!("Usage store: {}", e))
+}
+
+/// Usage rows of every organization, and reports over them
+pub struct OrgUsageStore {
+    pools: SqlitePools,
+    /// Model steps without a known model are priced as
+    default_model: String,
+    cache: DashMap<String, (Instant, OrgUsage)>,
+}
+
+impl OrgUsageStore {
+    pub fn new(pools: impl Into<SqlitePools>, default_model: impl Into<String>) -> Self {
+        Self {
+            pools: pools.into(),
+            default_model: default_model.into(),
+            cache: DashMap::new(),
+        }
+    }
+
+    /// The auth database the usage rows are kept in
+    pub fn pools(&self) -> &SqlitePools {
+        &self.pools
+    }
+
+    /// Create the usage tables
+    pub async fn initialize(&self) -> Result<()> {
+        let statements = [
+            r#"
+            CREATE TABLE IF NOT EXISTS usage_chains (
+                chain_id TEXT PRIMARY KEY,
+                org_id TEXT NOT NULL,
+                user_id TEXT,
+                status TEXT NOT NULL,
+                created_at INTEGER NOT NULL,
+                duration_ms INTEGER
+            )
+            "#,
+            "CREATE INDEX IF NOT EXISTS idx_usage_chains_org ON usage_chains(org_id, created_at)",
+            r#"
+            CREATE TABLE IF NOT EXISTS usage_steps (
+                chain_id TEXT NOT NULL,
+                org_id TEXT NOT NULL,
+                user_id TEXT,
+                layer TEXT NOT NULL,
+                model TEXT NOT NULL,
+                prompt_tokens INTEGER NOT NULL,
+                completion_tokens INTEGER NOT NULL,
+                cost_usd REAL NOT NULL,
+                created_at INTEGER NOT NULL
+            )
+            "#,
+            "CREATE INDEX IF NOT EXISTS idx_usage_steps_org ON usage_steps(org_id, created_at)",
+            "CREATE INDEX IF NOT EXISTS idx_usage_steps_chain ON usage_steps(chain_id)",
+            r#"
+            CREATE TABLE IF NOT EXISTS usage_deliveries (
+                delivery_id TEXT PRIMARY KEY,
+                org_id TEXT NOT NULL,
+                delivered BOOLEAN NOT NULL,
+                created_at INTEGER NOT NULL
+            )
+            "#,
+            "CREATE INDEX IF NOT EXISTS idx_usage_deliveries_org ON usage_deliveries(org_id, created_at)",
+            r#"
+            CREATE TABLE IF NOT EXISTS usage_dead_letters (
+                signal_id TEXT NOT NULL,
+                org_id TEXT NOT NULL,
+                neuron_id TEXT NOT NULL,
+                reason TEXT NOT NULL,
+                created_at INTEGER NOT NULL
+            )
+            "#,
+            "CREATE INDEX IF NOT EXISTS idx_usage_dead_letters_org ON usage_dead_letters(org_id, created_at)",
+        ];
+        for statement in statements {
+            sqlx::query(statement).execute(self.pools.writer()).await.map_err(storage_error)?;
+        }
+        Ok(())
+    }
+
+    /// Record a chain that has started. Chains without an organization are
+    /// not recorded.
+    pub async fn record_chain_started(&self, record: &ChainRecord) -> Result<()> {
+        let Some(org_id) = &record.org_id else {
+            return Ok(());
+        };
+        self.pools
+            .write(|pool| async move {
+                sqlx::query(
+                    r#"
+                    INSERT INTO usage_chains (chain_id, org_id, user_id, status, created_at)
+                    VALUES ($1, $2, $3, 'running', $4)
+                    ON CONFLICT(chain_id) DO NOTHING
+                    "#,
+                )
+                .bind(&record.chain_id)
+                .bind(org_id)
+                .bind(&record.user_id)
+                .bind(millis(record.created_at))
+                .execute(&pool)
+                .await
+            })
+            .await
+            .map_err(storage_error)?;
+        Ok(())
+    }
+
+    /// Record a finished chain with its steps, replacing what was recorded
+    /// for it before
+    pub async fn record_chain(&self, record: &ChainRecord) -> Result<()> {
+        let Some(org_id) = &record.org_id else {
+            return Ok(());
+        };
+        let created_at = millis(record.created_at);
+        let duration_ms = record.completed_at.map(|done| (done - record.created_at).num_milliseconds());
+        let status = status_name(record.status);
+        let default_model = self.default_model.as_str();
+
+        self.pools
+            .write(|pool| async move {
+                let mut tx = pool.begin().await?;
+                sqlx::query(
+                    r#"
+                    INSERT INTO usage_chains (chain_id, org_id, user_id, status, created_at, duration_ms)
+                    VALUES ($1, $2, $3, $4, $5, $6)
+                    ON CONFLICT(chain_id) DO UPDATE SET status = excluded.status, duration_ms = excluded.duration_ms
+                    "#,
+                )
+                .bind(&record.chain_id)
+                .bind(org_id)
+                .bind(&record.user_id)
+                .bind(status)
+                .bind(created_at)
+                .bind(duration_ms)
+                .execute(&mut *tx)
+                .await?;
+
+                sqlx::query("DELETE FROM usage_steps WHERE chain_id = $1")
+                    .bind(&record.chain_id)
+                    .execute(&mut *tx)
+                    .await?;
+                for step in &record.steps {
+                    sqlx::query(
+                        r#"
+                        INSERT INTO usage_steps (chain_id, org_id, user_id, layer, model, prompt_tokens,
+                                                 completion_tokens, cost_usd, created_at)
+                        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+                        "#,
+                    )
+                    .bind(&record.chain_id)
+                    .bind(org_id)
+                    .bind(&record.user_id)
+                    .bind(&step.layer)
+                    .bind(step.model.as_deref().unwrap_or(default_model))
+                    .bind(step.prompt_tokens as i64)
+                    .bind(step.completion_tokens as i64)
+                    .bind(step_cost(step, default_model))
+                    .bind(created_at)
+                    .execute(&mut *tx)
+                    .await?;
+                }
+                tx.commit().await
+            })
+            .await
+            .map_err(storage_error)?;
+        Ok(())
+    }
+
+    /// Record a finished event delivery under the webhook's owner
+    pub async fn record_delivery(&self, finished: &FinishedDelivery) -> Result<()> {
+        let delivery = &finished.delivery;
+        let delivered = match delivery.status {
+            DeliveryStatus::Delivered => true,
+            DeliveryStatus::DeadLettered => false,
+            DeliveryStatus::Pending | DeliveryStatus::Retrying => return Ok(()),
+        };
+        self.pools
+            .write(|pool| async move {
+                sqlx::query(
+                    r#"
+                    INSERT INTO usage_deliveries (delivery_id, org_id, delivered, created_at)
+                    VALUES ($1, $2, $3, $4)
+                    ON CONFLICT(delivery_id) DO UPDATE SET delivered = excluded.delivered
+                    "#,
+                )
+                .bind(&delivery.id)
+                .bind(&finished.owner)
+                .bind(delivered)
+                .bind(millis(delivery.created_at))
+                .execute(&pool)
+                .await
+            })
+            .await
+            .map_err(storage_error)?;
+        Ok(())
+    }
+
+    /// Record a dead-lettered signal of an organization's chain
+    pub async fn record_dead_letter(&self, letter: &DeadLetter) -> Result<()> {
+        let Some(org_id) = letter.signal.metadata.get(ORG_ID_KEY) else {
+            return Ok(());
+        };
+        self.pools
+            .write(|pool| async move {
+                sqlx::query(
+                    r#"
+                    INSERT INTO usage_dead_letters (signal_id, org_id, neuron_id, reason, created_at)
+                    VALUES ($1, $2, $3, $4, $5)
+                    "#,
+                )
+                .bind(letter.signal.signal_id.to_string())
+                .bind(org_id)
+                .bind(&letter.neuron_id)
+                .bind(&letter.reason)
+                .bind(millis(letter.timestamp))
+                .execute(&pool)
+                .await
+            })
+            .await
+            .map_err(storage_error)?;
+        Ok(())
+    }
+
+    /// Usage of `org_id` over the period containing `now`, compared to the
+    /// period before it if `compare`
+    pub async fn report(&self, org_id: &str, period: BudgetPeriod, now: DateTime<Utc>, compare: bool) -> Result<OrgUsage> {
+        let start = period_start(period, utc(), now);
+        let key = format!("{}|{:?}|{}|{}", org_id, period, millis(start), compare);
+        if let Some(cached) = self.cache.get(&key) {
+            if cached.0.elapsed() < REPORT_CACHE_TTL {
+                return Ok(cached.1.clone());
+            }
+        }
+
+        let end = next_reset(period, utc(), start);
+        let mut usage = self.aggregate(org_id, period, start, end).await?;
+        if compare {
+            let previous_start = period_start(period, utc(), start - chrono::Duration::milliseconds(1));
+            let previous = self.aggregate(org_id, period, previous_start, start).await?;
+            usage.comparison = Some(UsageComparison {
+                period_start: previous_start,
+                period_end: start,
+                deltas: usage.deltas_since(&previous),
+            });
+        }
+
+        self.cache.retain(|_, (at, _)| at.elapsed() < REPORT_CACHE_TTL);
+        self.cache.insert(key, (Instant::now(), usage.clone()));
+        Ok(usage)
+    }
+
+    /// Aggregate the rows of `org_id` created in `[start, end)`
+    async fn aggregate(&self, org_id: &str, period: BudgetPeriod, start: DateTime<Utc>, end: DateTime<Utc>) -> Result<OrgUsage> {
+        let pool = self.pools.reader();
+        let (from, to) = (millis(start), millis(end));
+
+        let chains = sqlx::query(
+            r#"
+            SELECT COUNT(*) AS started,
+                   COALESCE(SUM(status = 'completed'), 0) AS completed,
+                   COALESCE(SUM(status = 'failed'), 0) AS failed,
+                   AVG(CASE WHEN status IN ('completed', 'failed') THEN duration_ms END) AS avg_latency_ms
+            FROM usage_chains
+            WHERE org_id = $1 AND created_at >= $2 AND created_at < $3
+            "#,
+        )
+        .bind(org_id)
+        .bind(from)
+        .bind(to)
+        .fetch_one(pool)
+        .await
+        .map_err(storage_error)?;
+
+        let breakdown = |column: &'static str| {
+            format!(
+                r#"
+                SELECT {column} AS name, SUM(prompt_tokens) AS prompt_tokens,
+                       SUM(completion_tokens) AS completion_tokens, SUM(cost_usd) AS cost_usd
+                FROM usage_steps
+                WHERE org_id = $1 AND created_at >= $2 AND created_at < $3
+                GROUP BY {column}
+                ORDER BY {column}
+                "#
+            )
+        };
+        let mut breakdowns = Vec::new();
+        for column in ["layer", "model"] {
+            let rows = sqlx::query(&breakdown(column))
+                .bind(org_id)
+                .bind(from)
+                .bind(to)
+                .fetch_all(pool)
+                .await
+                .map_err(storage_error)?;
+            breakdowns.push(
+                rows.iter()
+                    .map(|row| UsageBreakdown {
+                        name: row.get("name"),
+                        prompt_tokens: row.get::<i64, _>("prompt_tokens") as u64,
+                        completion_tokens: row.get::<i64, _>("completion_tokens") as u64,
+                        cost_usd: row.get("cost_usd"),
+                    })
+                    .collect::<Vec<_>>(),
+            );
+        }
+        let by_model = breakdowns.pop().unwrap_or_default();
+        let by_layer = breakdowns.pop().unwrap_or_default();
+
+        let top_users = sqlx::query(
+            r#"
+            SELECT user_id, COUNT(DISTINCT chain_id) AS chains,
+                   SUM(prompt_tokens + completion_tokens) AS tokens, SUM(cost_usd) AS cost_usd
+            FROM usage_steps
+            WHERE org_id = $1 AND created_at >= $2 AND created_at < $3 AND user_id IS NOT NULL
+            GROUP BY user_id
+            ORDER BY cost_usd DESC, user_id
+            LIMIT $4
+            "#,
+        )
+        .bind(org_id)
+        .bind(from)
+        .bind(to)
+        .bind(TOP_USERS)
+        .fetch_all(pool)
+        .await
+        .map_err(storage_error)?
+        .iter()
+        .map(|row| UserSpend {
+            user_id: row.get("user_id"),
+            chains: row.get::<i64, _>("chains") as u64,
+            tokens: row.get::<i64, _>("tokens") as u64,
+            cost_usd: row.get("cost_usd"),
+        })
+        .collect();
+
+        let deliveries = sqlx::query(
+            r#"
+            SELECT COUNT(*) AS total, COALESCE(SUM(delivered), 0) AS delivered
+            FROM usage_deliveries
+            WHERE org_id = $1 AND created_at >= $2 AND created_at < $3
+            "#,
+        )
+        .bind(org_id)
+        .bind(from)
+        .bind(to)
+        .fetch_one(pool)
+        .await
+        .map_err(storage_error)?;
+        let webhook_deliveries = deliveries.get::<i64, _>("total") as u64;
+        let delivered = deliveries.get::<i64, _>("delivered") as u64;
+
+        let dead_letters: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM usage_dead_letters WHERE org_id = $1 AND created_at >= $2 AND created_at < $3",
+        )
+        .bind(org_id)
+        .bind(from)
+        .bind(to)
+        .fetch_one(pool)
+        .await
+        .map_err(storage_error)?;
+
+        Ok(OrgUsage {
+            org_id: org_id.to_string(),
+            period,
+            period_start: start,
+            period_end: end,
+            chains_started: chains.get::<i64, _>("started") as u64,
+            chains_completed: chains.get::<i64, _>("completed") as u64,
+            chains_failed: chains.get::<i64, _>("failed") as u64,
+            prompt_tokens: by_layer.iter().map(|b| b.prompt_tokens).sum(),
+            completion_tokens: by_layer.iter().map(|b| b.completion_tokens).sum(),
+            cost_usd: by_layer.iter().map(|b| b.cost_usd).sum(),
+            by_layer,
+            by_model,
+            top_users,
+            webhook_deliveries,
+            webhook_success_rate: (webhook_deliveries > 0).then(|| delivered as f64 / webhook_deliveries as f64),
+            dead_letters: dead_letters as u64,
+            avg_chain_latency_ms: chains.get("avg_latency_ms"),
+            comparison: None,
+        })
+    }
+}
+
+/// Render a report as CSV, one `metric,dimension,value` row per number.
+/// Compared reports add a `delta` column to the headline rows.
+pub fn to_csv(usage: &OrgUsage) -> String {
+    let deltas = usage.comparison.as_ref().map(|c| &c.deltas);
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    let mut header = vec!["metric", "dimension", "value"];
+    if deltas.is_some() {
+        header.push("delta");
+    }
+    let _ = writer.write_record(&header);
+
+    let mut row = |metric: &str, dimension: &str, value: String, delta: Option<String>| {
+        let mut record = vec![metric.to_string(), dimension.to_string(), value];
+        if deltas.is_some() {
+            record.push(delta.unwrap_or_default());
+        }
+        let _ = writer.write_record(&record);
+    };
+    let optional = |value: Option<f64>| value.map(|v| v.to_string()).unwrap_or_default();
+
+    row("chains_started", "", usage.chains_started.to_string(), deltas.map(|d| d.chains_started.to_string()));
+    row("chains_completed", "", usage.chains_completed.to_string(), deltas.map(|d| d.chains_completed.to_string()));
+    row("chains_failed", "", usage.chains_failed.to_string(), deltas.map(|d| d.chains_failed.to_string()));
+    row("prompt_tokens", "", usage.prompt_tokens.to_string(), None);
+    row("completion_tokens", "", usage.completion_tokens.to_string(), None);
+    row("tokens", "", usage.tokens().to_string(), deltas.map(|d| d.tokens.to_string()));
+    row("cost_usd", "", usage.cost_usd.to_string(), deltas.map(|d| d.cost_usd.to_string()));
+    for (kind, breakdowns) in [("layer", &usage.by_layer), ("model", &usage.by_model)] {
+        for b in breakdowns {
+            let dimension = format!("{}:{}", kind, b.name);
+            row("prompt_tokens", &dimension, b.prompt_tokens.to_string(), None);
+            row("completion_tokens", &dimension, b.completion_tokens.to_string(), None);
+            row("cost_usd", &dimension, b.cost_usd.to_string(), None);
+        }
+    }
+    for user in &usage.top_users {
+        row("cost_usd", &format!("user:{}", user.user_id), user.cost_usd.to_string(), None);
+    }
+    row("webhook_deliveries", "", usage.webhook_deliveries.to_string(), None);
+    row(
+        "webhook_success_rate",
+        "",
+        optional(usage.webhook_success_rate),
+        deltas.map(|d| optional(d.webhook_success_rate)),
+    );
+    row("dead_letters", "", usage.dead_letters.to_string(), deltas.map(|d| d.dead_letters.to_string()));
+    row(
+        "avg_chain_latency_ms",
+        "",
+        optional(usage.avg_chain_latency_ms),
+        deltas.map(|d| optional(d.avg_chain_latency_ms)),
+    );
+
+    String::from_utf8(writer.into_inner().unwrap_or_default()).unwrap_or_default()
+}
+
+/// Record organizations' chains, webhook deliveries and dead letters as
+/// they happen
+pub async fn recorder_task(
+    store: Arc<OrgUsageStore>,
+    chain_tracker: Arc<ChainTracker>,
+    mut started: broadcast::Receiver<String>,
+    mut finished: broadcast::Receiver<ChainResult>,
+    mut deliveries: broadcast::Receiver<FinishedDelivery>,
+    mut dead_letters: broadcast::Receiver<DeadLetter>,
+) {
+    use broadcast::error::RecvError;
+
+    /// Whether to keep recording after a receive error
+    fn keep_going(source: &str, e: &RecvError) -> bool {
+        if let RecvError::Lagged(skipped) = e {
+            error!("Usage recorder lagged, {} {} not recorded", skipped, source);
+        }
+        matches!(e, RecvError::Lagged(_))
+    }
+
+    loop {
+        let recorded = tokio::select! {
+            result = started.recv() => match result.map(|id| chain_tracker.get(&id)) {
+                Ok(Some(record)) => store.record_chain_started(&record).await,
+                Ok(None) => continue,
+                Err(e) if keep_going("started chains", &e) => continue,
+                Err(_) => break,
+            },
+            result = finished.recv() => match result.map(|r| chain_tracker.get(&r.chain_id)) {
+                Ok(Some(record)) => store.record_chain(&record).await,
+                Ok(None) => continue,
+                Err(e) if keep_going("finished chains", &e) => continue,
+                Err(_) => break,
+            },
+            result = deliveries.recv() => match result {
+                Ok(delivery) => store.record_delivery(&delivery).await,
+                Err(e) if keep_going("webhook deliveries", &e) => continue,
+                Err(_) => break,
+            },
+            result = dead_letters.recv() => match result {
+                Ok(letter) => store.record_dead_letter(&letter).await,
+                Err(e) if keep_going("dead letters", &e) => continue,
+                Err(_) => break,
+            },
+        };
+        if let Err(e) = recorded {
+            error!("Failed to record organization usage: {}", e);
+        }
+    }
+}
@@ -0,0 +1,143 @@
+//! Output format negotiation for chain results
+//!
+//! A submitter can ask for a chain's final output as markdown, JSON
+//! (optionally matching a JSON Schema) or plain text. The request travels
+//! with the chain in signal metadata (`output.format`, `output.schema`) and
+//! is acted on by terminal neurons, those without forward connections: the
+//! format's instructions are added to their prompt and their output is
+//! checked with the matching response validator. An output that fails is
+//! handed to a cheaper model to reformat, up to
+//! `output_format.max_reformat_retries` times, and is returned as it was
+//! with `format_degraded` set on the chain result if it still fails.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use hal9_core::{metadata_schema::keys, Error, Result};
+
+use crate::validation::{CodeFenceValidator, JsonSchemaValidator, PlainTextValidator, ResponseValidator};
+
+pub use hal9_api_types::OutputFormat;
+
+/// Signal metadata key holding the requested output format
+pub const OUTPUT_FORMAT_KEY: &str = keys::output::FORMAT;
+/// Signal metadata key holding the JSON Schema of a json output
+pub const OUTPUT_SCHEMA_KEY: &str = keys::output::SCHEMA;
+
+/// Record the requested format of a chain's output in its root signal's
+/// metadata
+pub fn request_format(metadata: &mut HashMap<String, String>, format: OutputFormat, schema: Option<&Value>) {
+    metadata.insert(OUTPUT_FORMAT_KEY.to_string(), format.as_str().to_string());
+    if let Some(schema) = schema {
+        metadata.insert(OUTPUT_SCHEMA_KEY.to_string(), schema.to_string());
+    }
+}
+
+/// A requested output format and the validator that checks it
+pub struct FormatRequest {
+    format: OutputFormat,
+    schema: Option<Value>,
+    validator: Box<dyn ResponseValidator>,
+}
+
+impl FormatRequest {
+    /// Fails for a schema that is not a valid JSON Schema, or one given
+    /// with a format other than json
+    pub fn new(format: OutputFormat, schema: Option<Value>) -> Result<Self> {
+        if schema.is_some() && format != OutputFormat::Json {
+            return Err(Error::InvalidInput(format!(
+                "An output schema needs the json output format, not {}",
+                format.as_str()
+            )));
+        }
+        let validator: Box<dyn ResponseValidator> = match format {
+            OutputFormat::Markdown => Box::new(CodeFenceValidator),
+            OutputFormat::Json => {
+                let schema = schema.clone().unwrap_or_else(|| json!({}));
+                Box::new(JsonSchemaValidator::new(&schema).map_err(|e| match e {
+                    Error::Config(reason) => Error::InvalidInput(reason),
+                    e => e,
+                })?)
+            }
+            OutputFormat::Text => Box::new(PlainTextValidator),
+        };
+        Ok(Self { format, schema, validator })
+    }
+
+    /// Format requested in signal metadata, `None` if none was
+    pub fn from_metadata(metadata: &HashMap<String, String>) -> Result<Option<Self>> {
+        let Some(format) = metadata.get(OUTPUT_FORMAT_KEY) else {
+            return Ok(None);
+        };
+        let format = OutputFormat::parse(format).ok_or_else(|| {
+            Error::InvalidInput(format!("Unknown output format '{}'; expected markdown, json or text", format))
+        })?;
+        let schema = metadata
+            .get(OUTPUT_SCHEMA_KEY)
+            .map(|schema| serde_json::from_str(schema))
+            .transpose()
+            .map_err(|e| Error::InvalidInput(format!("Output schema is not valid JSON: {}", e)))?;
+        Self::new(format, schema).map(Some)
+    }
+
+    pub fn format(&self) -> OutputFormat {
+        self.format
+    }
+
+    /// Name of the validator, as recorded in validation metrics
+    pub fn validator(&self) -> &'static str {
+        self.validator.name()
+    }
+
+    /// Instructions added to a terminal neuron's prompt. They avoid the
+    /// keywords mock Claude answers with canned templates.
+    pub fn instructions(&self) -> String {
+        match self.format {
+            OutputFormat::Markdown => "OUTPUT FORMAT: Respond in Markdown. Use headings and lists where they help, \
+                 and close every fenced block you open."
+                .to_string(),
+            OutputFormat::Json => {
+                let mut instructions = "OUTPUT FORMAT: Respond with a single JSON value and nothing else: \
+                     no prose and no fences."
+                    .to_string();
+                if let Some(schema) = &self.schema {
+                    instructions.push_str(&format!(" It must match this JSON Schema:\n{}", schema));
+                }
+                instructions
+            }
+            OutputFormat::Text => "OUTPUT FORMAT: Respond in plain text without any Markdown: \
+                 no headings, fenced blocks, bold text or links."
+                .to_string(),
+        }
+    }
+
+    /// `Err` carries the reason the response does not conform
+    pub fn check(&self, response: &str) -> std::result::Result<(), String> {
+        self.validator.validate(response)
+    }
+
+    /// Prompt asking a model to rewrite `response` in this format
+    pub fn reformat_prompt(&self, response: &str, reason: &str) -> String {
+        format!(
+            "Rewrite the following output so that it conforms to the format below. Keep its content; \
+             change only its form. Respond with the rewritten output alone.\n\n{}\n\n\
+             The output failed the format check because {}.\n\nOUTPUT:\n{}",
+            self.instructions(),
+            reason,
+            response
+        )
+    }
+}
+
+/// How a terminal neuron's output fared against its requested format
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FormatReport {
+    pub format: OutputFormat,
+    /// The output still fails the format check and is returned as it was
+    pub degraded: bool,
+    /// Why the output last failed the check
+    pub reason: Option<String>,
+    pub reformat_retries: u32,
+}
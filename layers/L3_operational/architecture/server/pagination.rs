@@ -0,0 +1,421 @@
+//! Cursor pagination, sorting and filtering shared by list endpoints
+//!
+//! Every list endpoint accepts the same query grammar:
+//!
+//! - `limit=<n>`: page size, capped at `MAX_LIMIT`
+//! - `cursor=<opaque>`: `next_cursor` from the previous page
+//! - `sort=<field>` or `sort=-<field>` for descending order
+//! - `<field>=<value>[,<value>...]`: keep items whose field equals any value
+//!
+//! and answers with `{items, next_cursor, total_estimate}`. Cursors encode
+//! the sort key and id of the last item returned (keyset pagination), so
+//! rows inserted while a client walks the pages never shift an existing row
+//! into a page it has already read.
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use hal9_core::memory::MemoryEntry;
+
+use crate::approvals::PendingApproval;
+use crate::chain_tracker::SignalActivity;
+use crate::concurrency::DeadLetter;
+use crate::error::{ServerError, ServerResult};
+use crate::error_recovery::ErrorContext;
+use crate::ingestion::quarantine::QuarantinedMessage;
+use crate::quality::QualityRun;
+use crate::self_organizer::ScalingEvent;
+use crate::server::NeuronInfo;
+use crate::webhooks::{Delivery, Webhook};
+
+pub use hal9_api_types::Page;
+
+/// Page size when `limit` is not given
+pub const DEFAULT_LIMIT: usize = 50;
+/// Largest page size a client may request
+pub const MAX_LIMIT: usize = 500;
+
+/// Value an item is sorted by
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum SortKey {
+    Int(i64),
+    Str(String),
+}
+
+impl From<DateTime<Utc>> for SortKey {
+    fn from(at: DateTime<Utc>) -> Self {
+        SortKey::Int(at.timestamp_micros())
+    }
+}
+
+impl From<&str> for SortKey {
+    fn from(s: &str) -> Self {
+        SortKey::Str(s.to_string())
+    }
+}
+
+/// An item that list endpoints can page through
+pub trait Listable: Serialize {
+    /// Sort used when the request names none, in `sort` syntax
+    const DEFAULT_SORT: &'static str;
+    /// Fields accepted by `sort`
+    const SORT_FIELDS: &'static [&'static str];
+    /// Fields accepted as filters
+    const FILTER_FIELDS: &'static [&'static str];
+
+    /// Unique, stable id breaking ties between equal sort keys
+    fn list_id(&self) -> String;
+
+    /// Key for one of `SORT_FIELDS`
+    fn sort_key(&self, field: &str) -> SortKey;
+
+    /// Value compared against a filter; defaults to the serialized field
+    fn filter_value(&self, field: &str) -> Option<String> {
+        match serde_json::to_value(self).ok()?.get(field)? {
+            Value::String(s) => Some(s.clone()),
+            Value::Null => None,
+            other => Some(other.to_string()),
+        }
+    }
+}
+
+/// Parsed list query parameters
+#[derive(Debug, Clone)]
+pub struct ListParams {
+    pub limit: usize,
+    pub cursor: Option<String>,
+    pub sort_field: String,
+    pub descending: bool,
+    pub filters: Vec<(String, Vec<String>)>,
+}
+
+impl ListParams {
+    /// Parse raw query parameters for items of type `T`. `reserved` names
+    /// endpoint-specific parameters that are neither filters nor errors.
+    pub fn parse<T: Listable>(query: &HashMap<String, String>, reserved: &[&str]) -> ServerResult<Self> {
+        let limit = match query.get("limit") {
+            Some(limit) => limit.parse::<usize>()
+                .map_err(|_| ServerError::InvalidInput(format!("Invalid limit '{}'", limit)))?
+                .clamp(1, MAX_LIMIT),
+            None => DEFAULT_LIMIT,
+        };
+
+        let sort = query.get("sort").map(String::as_str).unwrap_or(T::DEFAULT_SORT);
+        let (sort_field, descending) = match sort.strip_prefix('-') {
+            Some(field) => (field, true),
+            None => (sort, false),
+        };
+        if !T::SORT_FIELDS.contains(&sort_field) {
+            return Err(ServerError::InvalidInput(format!(
+                "Cannot sort by '{}'; expected one of {}",
+                sort_field,
+                T::SORT_FIELDS.join(", ")
+            )));
+        }
+
+        let mut filters = Vec::new();
+        for (field, value) in query {
+            if matches!(field.as_str(), "limit" | "cursor" | "sort") || reserved.contains(&field.as_str()) {
+                continue;
+            }
+            if !T::FILTER_FIELDS.contains(&field.as_str()) {
+                return Err(ServerError::InvalidInput(format!(
+                    "Cannot filter by '{}'; expected one of {}",
+                    field,
+                    T::FILTER_FIELDS.join(", ")
+                )));
+            }
+            filters.push((field.clone(), value.split(',').map(str::to_string).collect()));
+        }
+        filters.sort();
+
+        Ok(Self {
+            limit,
+            cursor: query.get("cursor").cloned(),
+            sort_field: sort_field.to_string(),
+            descending,
+            filters,
+        })
+    }
+
+    fn sort_spec(&self) -> String {
+        if self.descending {
+            format!("-{}", self.sort_field)
+        } else {
+            self.sort_field.clone()
+        }
+    }
+}
+
+/// Position after the last item of a page
+#[derive(Debug, Serialize, Deserialize)]
+struct Cursor {
+    /// Sort the cursor was issued for
+    sort: String,
+    key: SortKey,
+    id: String,
+}
+
+impl Cursor {
+    fn encode(&self) -> String {
+        URL_SAFE_NO_PAD.encode(serde_json::to_vec(self).unwrap_or_default())
+    }
+
+    fn decode(cursor: &str) -> ServerResult<Self> {
+        URL_SAFE_NO_PAD.decode(cursor).ok()
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            .ok_or_else(|| ServerError::InvalidInput("Invalid cursor".to_string()))
+    }
+}
+
+/// Filter, sort and slice `items` into one page
+pub fn paginate<T: Listable>(items: Vec<T>, params: &ListParams) -> ServerResult<Page<T>> {
+    let after = params.cursor.as_deref().map(Cursor::decode).transpose()?;
+    let sort = params.sort_spec();
+    if let Some(after) = &after {
+        if after.sort != sort {
+            return Err(ServerError::InvalidInput(format!(
+                "Cursor was issued for sort '{}', not '{}'",
+                after.sort, sort
+            )));
+        }
+    }
+
+    let mut keyed: Vec<(SortKey, String, T)> = items.into_iter()
+        .filter(|item| {
+            params.filters.iter().all(|(field, values)| {
+                item.filter_value(field).is_some_and(|v| values.contains(&v))
+            })
+        })
+        .map(|item| (item.sort_key(&params.sort_field), item.list_id(), item))
+        .collect();
+
+    let compare = |a_key: &SortKey, a_id: &str, b_key: &SortKey, b_id: &str| {
+        let ordering = a_key.cmp(b_key).then_with(|| a_id.cmp(b_id));
+        if params.descending { ordering.reverse() } else { ordering }
+    };
+    keyed.sort_by(|a, b| compare(&a.0, &a.1, &b.0, &b.1));
+    let total_estimate = keyed.len();
+
+    // Resume strictly after the cursor position, whether or not that item
+    // still exists
+    let start = match &after {
+        Some(after) => keyed.partition_point(|(key, id, _)| {
+            compare(key, id, &after.key, &after.id) != Ordering::Greater
+        }),
+        None => 0,
+    };
+
+    let mut page: Vec<(SortKey, String, T)> = keyed.into_iter().skip(start).take(params.limit + 1).collect();
+    let next_cursor = if page.len() > params.limit {
+        page.truncate(params.limit);
+        page.last().map(|(key, id, _)| Cursor { sort, key: key.clone(), id: id.clone() }.encode())
+    } else {
+        None
+    };
+
+    Ok(Page {
+        items: page.into_iter().map(|(_, _, item)| item).collect(),
+        next_cursor,
+        total_estimate,
+    })
+}
+
+impl Listable for NeuronInfo {
+    const DEFAULT_SORT: &'static str = "id";
+    const SORT_FIELDS: &'static [&'static str] = &["id", "layer", "state", "queued", "rejected"];
+    const FILTER_FIELDS: &'static [&'static str] = &["layer", "state", "is_healthy"];
+
+    fn list_id(&self) -> String {
+        self.id.clone()
+    }
+
+    fn sort_key(&self, field: &str) -> SortKey {
+        match field {
+            "layer" => self.layer.as_str().into(),
+            "state" => self.state.as_str().into(),
+            "queued" => SortKey::Int(self.concurrency.queued as i64),
+            "rejected" => SortKey::Int(self.concurrency.rejected as i64),
+            _ => self.id.as_str().into(),
+        }
+    }
+}
+
+impl Listable for ScalingEvent {
+    const DEFAULT_SORT: &'static str = "timestamp";
+    const SORT_FIELDS: &'static [&'static str] = &["timestamp", "layer"];
+    const FILTER_FIELDS: &'static [&'static str] = &["layer", "action", "source_neuron"];
+
+    fn list_id(&self) -> String {
+        format!(
+            "{}/{}/{:?}",
+            self.source_neuron,
+            self.clone_id.as_deref().unwrap_or_default(),
+            self.action
+        )
+    }
+
+    fn sort_key(&self, field: &str) -> SortKey {
+        match field {
+            "layer" => self.layer.as_str().into(),
+            _ => self.timestamp.into(),
+        }
+    }
+}
+
+impl Listable for DeadLetter {
+    const DEFAULT_SORT: &'static str = "-timestamp";
+    const SORT_FIELDS: &'static [&'static str] = &["timestamp", "neuron_id"];
+    const FILTER_FIELDS: &'static [&'static str] = &["neuron_id", "reason"];
+
+    fn list_id(&self) -> String {
+        self.signal.signal_id.to_string()
+    }
+
+    fn sort_key(&self, field: &str) -> SortKey {
+        match field {
+            "neuron_id" => self.neuron_id.as_str().into(),
+            _ => self.timestamp.into(),
+        }
+    }
+}
+
+impl Listable for PendingApproval {
+    const DEFAULT_SORT: &'static str = "requested_at";
+    const SORT_FIELDS: &'static [&'static str] = &["requested_at", "expires_at", "neuron_id"];
+    const FILTER_FIELDS: &'static [&'static str] = &["chain_id", "neuron_id", "layer"];
+
+    fn list_id(&self) -> String {
+        self.id.clone()
+    }
+
+    fn sort_key(&self, field: &str) -> SortKey {
+        match field {
+            "expires_at" => self.expires_at.into(),
+            "neuron_id" => self.neuron_id.as_str().into(),
+            _ => self.requested_at.into(),
+        }
+    }
+}
+
+impl Listable for MemoryEntry {
+    const DEFAULT_SORT: &'static str = "-timestamp";
+    const SORT_FIELDS: &'static [&'static str] = &["timestamp", "neuron_id"];
+    const FILTER_FIELDS: &'static [&'static str] = &["namespace", "neuron_id", "layer"];
+
+    fn list_id(&self) -> String {
+        self.id.to_string()
+    }
+
+    fn sort_key(&self, field: &str) -> SortKey {
+        match field {
+            "neuron_id" => self.neuron_id.as_str().into(),
+            _ => self.timestamp.into(),
+        }
+    }
+}
+
+impl Listable for Webhook {
+    const DEFAULT_SORT: &'static str = "created_at";
+    const SORT_FIELDS: &'static [&'static str] = &["created_at", "url"];
+    const FILTER_FIELDS: &'static [&'static str] = &["url"];
+
+    fn list_id(&self) -> String {
+        self.id.clone()
+    }
+
+    fn sort_key(&self, field: &str) -> SortKey {
+        match field {
+            "url" => self.url.as_str().into(),
+            _ => self.created_at.into(),
+        }
+    }
+}
+
+impl Listable for Delivery {
+    const DEFAULT_SORT: &'static str = "-created_at";
+    const SORT_FIELDS: &'static [&'static str] = &["created_at"];
+    /// `status=dead_lettered` lists the dead-letter queue
+    const FILTER_FIELDS: &'static [&'static str] = &["status", "event"];
+
+    fn list_id(&self) -> String {
+        self.id.clone()
+    }
+
+    fn sort_key(&self, _field: &str) -> SortKey {
+        self.created_at.into()
+    }
+}
+
+impl Listable for ErrorContext {
+    const DEFAULT_SORT: &'static str = "-timestamp";
+    const SORT_FIELDS: &'static [&'static str] = &["timestamp", "error_type"];
+    const FILTER_FIELDS: &'static [&'static str] = &["error_type", "path", "method", "trace_id"];
+
+    fn list_id(&self) -> String {
+        self.error_id.clone()
+    }
+
+    fn sort_key(&self, field: &str) -> SortKey {
+        match field {
+            "error_type" => self.error_type.as_str().into(),
+            _ => DateTime::parse_from_rfc3339(&self.timestamp)
+                .map(|at| SortKey::from(at.with_timezone(&Utc)))
+                .unwrap_or(SortKey::Int(0)),
+        }
+    }
+}
+
+impl Listable for SignalActivity {
+    const DEFAULT_SORT: &'static str = "-timestamp";
+    const SORT_FIELDS: &'static [&'static str] = &["timestamp", "neuron_id"];
+    const FILTER_FIELDS: &'static [&'static str] = &["neuron_id", "layer", "chain_id"];
+
+    fn list_id(&self) -> String {
+        self.signal_id.clone()
+    }
+
+    fn sort_key(&self, field: &str) -> SortKey {
+        match field {
+            "neuron_id" => self.neuron_id.as_str().into(),
+            _ => self.timestamp.into(),
+        }
+    }
+}
+
+impl Listable for QualityRun {
+    const DEFAULT_SORT: &'static str = "-started_at";
+    const SORT_FIELDS: &'static [&'static str] = &["started_at"];
+    const FILTER_FIELDS: &'static [&'static str] = &["triggered_by", "config_hash", "candidate"];
+
+    fn list_id(&self) -> String {
+        self.id.clone()
+    }
+
+    fn sort_key(&self, _field: &str) -> SortKey {
+        self.started_at.into()
+    }
+}
+
+impl Listable for QuarantinedMessage {
+    const DEFAULT_SORT: &'static str = "-received_at";
+    const SORT_FIELDS: &'static [&'static str] = &["received_at", "source"];
+    const FILTER_FIELDS: &'static [&'static str] = &["source"];
+
+    fn list_id(&self) -> String {
+        self.id.clone()
+    }
+
+    fn sort_key(&self, field: &str) -> SortKey {
+        match field {
+            "source" => self.source.as_str().into(),
+            _ => self.received_at.into(),
+        }
+    }
+}
@@ -1,11 +1,43 @@
 //! Performance optimization utilities
+//!
+//! Also the built-in profiling for latency spikes that could be Claude, the
+//! database or a task starving the executor. While `profiling` is on:
+//!
+//! - `GET /debug/pprof/profile?seconds=` samples the CPU with pprof-rs and
+//!   returns a pprof protobuf. One profile is taken at a time, for at most
+//!   `profiling.max_profile_secs`, at `profiling.frequency_hz`; outside a
+//!   profile no samples are taken.
+//! - Tasks processing signals are spawned through [`PollMonitor`], which
+//!   times every poll: two clock reads and a few atomic operations each.
+//!   Polls over `profiling.poll_budget_ms` are counted, and ones over
+//!   `profiling.blocking_poll_ms` are logged with the place their task was
+//!   spawned. A watchdog thread, which a starved executor cannot hold up,
+//!   reports polls that are still blocking.
+//!
+//! Tasks spawned while profiling is off are spawned as they are, and the
+//! watchdog is parked: switched off, profiling costs nothing.
 
-use std::sync::Arc;
+use std::collections::VecDeque;
+use std::future::Future;
+use std::panic::Location;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Weak};
+use std::task::{Context, Poll};
 use std::time::{Duration, Instant};
+use chrono::{DateTime, Utc};
 use dashmap::DashMap;
-use parking_lot::RwLock;
+use parking_lot::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
 use tokio::sync::{Semaphore, mpsc};
-use tracing::debug;
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
+
+use hal9_core::config::ProfilingConfig;
+use hal9_core::Error;
+
+use crate::error::{ServerError, ServerResult};
+use crate::metrics::{DurationHistogram, HistogramStats};
 
 /// Connection pool for reusing resources
 #[allow(dead_code)]
@@ -422,6 +454,401 @@ pub struct InternerStats {
     pub total_bytes: usize,
 }
 
+/// Highest `profiling.frequency_hz`
+const MAX_FREQUENCY_HZ: i32 = 1000;
+
+/// Highest `profiling.max_profile_secs`
+const MAX_PROFILE_SECS: u64 = 300;
+
+/// Length of a CPU profile when none is asked for
+const DEFAULT_PROFILE_SECS: u64 = 30;
+
+/// A CPU profile is being taken. pprof samples the whole process, one
+/// profile at a time.
+static PROFILING_CPU: AtomicBool = AtomicBool::new(false);
+
+/// Stalls kept for `GET /api/v1/admin/profiling`
+const RECENT_STALLS: usize = 50;
+
+pub fn validate(config: &ProfilingConfig) -> hal9_core::Result<()> {
+    let invalid = |reason: &str| Err(Error::Config(format!("Invalid profiling config: {}", reason)));
+    if !(1..=MAX_FREQUENCY_HZ).contains(&config.frequency_hz) {
+        return invalid("frequency_hz must be between 1 and 1000");
+    }
+    if config.max_profile_secs == 0 || config.max_profile_secs > MAX_PROFILE_SECS {
+        return invalid("max_profile_secs must be between 1 and 300");
+    }
+    if config.poll_budget_ms == 0 || config.watchdog_interval_ms == 0 {
+        return invalid("poll_budget_ms and watchdog_interval_ms must be positive");
+    }
+    if config.blocking_poll_ms < config.poll_budget_ms {
+        return invalid("blocking_poll_ms must be at least poll_budget_ms");
+    }
+    Ok(())
+}
+
+/// A poll that blocked its worker thread for `profiling.blocking_poll_ms` or
+/// longer
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskStall {
+    /// Where the task was spawned, `file:line:column`
+    pub location: String,
+    /// How long the poll had blocked when it was reported
+    pub blocked_ms: u64,
+    /// Reported by the watchdog while the poll was still running
+    pub in_progress: bool,
+    pub at: DateTime<Utc>,
+}
+
+/// Poll times of monitored tasks since startup
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PollStats {
+    /// Monitored tasks not finished yet
+    pub tasks: usize,
+    pub polls: HistogramStats,
+    /// Polls longer than `profiling.poll_budget_ms`
+    pub over_budget: u64,
+    /// Polls longer than `profiling.blocking_poll_ms`
+    pub stalls: u64,
+    /// The latest stalls, newest last
+    pub recent_stalls: Vec<TaskStall>,
+}
+
+/// When the task's current poll started
+struct TaskClock {
+    location: &'static Location<'static>,
+    /// Nanoseconds since the monitor's epoch, plus one; 0 between polls
+    polling_since: AtomicU64,
+    /// The watchdog reported the current poll already
+    reported: AtomicBool,
+}
+
+/// Times the polls of the tasks spawned through it and reports the ones
+/// blocking their worker thread
+pub struct PollMonitor {
+    active: AtomicBool,
+    budget: Duration,
+    blocking: Duration,
+    epoch: Instant,
+    tasks: DashMap<u64, Arc<TaskClock>>,
+    next_task: AtomicU64,
+    polls: DurationHistogram,
+    over_budget: AtomicU64,
+    stalls: AtomicU64,
+    recent_stalls: Mutex<VecDeque<TaskStall>>,
+    watchdog_interval: Duration,
+    watchdog: Mutex<Option<std::thread::Thread>>,
+}
+
+impl PollMonitor {
+    /// A monitor, switched on if `config.enabled`
+    pub fn new(config: &ProfilingConfig) -> Arc<Self> {
+        let monitor = Arc::new(Self {
+            active: AtomicBool::new(false),
+            budget: Duration::from_millis(config.poll_budget_ms),
+            blocking: Duration::from_millis(config.blocking_poll_ms),
+            epoch: Instant::now(),
+            tasks: DashMap::new(),
+            next_task: AtomicU64::new(0),
+            polls: DurationHistogram::default(),
+            over_budget: AtomicU64::new(0),
+            stalls: AtomicU64::new(0),
+            recent_stalls: Mutex::new(VecDeque::new()),
+            watchdog_interval: Duration::from_millis(config.watchdog_interval_ms),
+            watchdog: Mutex::new(None),
+        });
+        if config.enabled {
+            monitor.set_active(true);
+        }
+        monitor
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.active.load(Ordering::Relaxed)
+    }
+
+    /// Start or stop timing polls. The watchdog thread is started the first
+    /// time, and parks while the monitor is off.
+    pub fn set_active(self: &Arc<Self>, active: bool) {
+        self.active.store(active, Ordering::SeqCst);
+        if !active {
+            return;
+        }
+        let mut watchdog = self.watchdog.lock();
+        match watchdog.as_ref() {
+            Some(thread) => thread.unpark(),
+            None => {
+                let monitor = Arc::downgrade(self);
+                let spawned = std::thread::Builder::new()
+                    .name("hal9-poll-watchdog".to_string())
+                    .spawn(move || watch(monitor));
+                match spawned {
+                    Ok(handle) => *watchdog = Some(handle.thread().clone()),
+                    Err(e) => warn!("Failed to start the poll watchdog: {}", e),
+                }
+            }
+        }
+    }
+
+    /// Spawn `future`, timing its polls while the monitor is on. Stalls are
+    /// reported with the caller's location.
+    #[track_caller]
+    pub fn spawn<F>(self: &Arc<Self>, future: F) -> JoinHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        if !self.is_active() {
+            return tokio::spawn(future);
+        }
+        let id = self.next_task.fetch_add(1, Ordering::Relaxed);
+        let clock = Arc::new(TaskClock {
+            location: Location::caller(),
+            polling_since: AtomicU64::new(0),
+            reported: AtomicBool::new(false),
+        });
+        self.tasks.insert(id, clock.clone());
+        tokio::spawn(Monitored {
+            monitor: self.clone(),
+            id,
+            clock,
+            future: Box::pin(future),
+        })
+    }
+
+    /// Report polls blocking for `profiling.blocking_poll_ms` or longer that
+    /// have not returned yet; each poll is reported once
+    pub fn check(&self) -> Vec<TaskStall> {
+        let now = self.epoch.elapsed().as_nanos() as u64 + 1;
+        let mut found = Vec::new();
+        for task in self.tasks.iter() {
+            let since = task.polling_since.load(Ordering::Acquire);
+            if since == 0 {
+                continue;
+            }
+            let blocked = Duration::from_nanos(now.saturating_sub(since));
+            if blocked >= self.blocking && !task.reported.swap(true, Ordering::AcqRel) {
+                found.push(self.stall(task.location, blocked, true));
+            }
+        }
+        found
+    }
+
+    pub fn stats(&self) -> PollStats {
+        PollStats {
+            tasks: self.tasks.len(),
+            polls: self.polls.stats(),
+            over_budget: self.over_budget.load(Ordering::Relaxed),
+            stalls: self.stalls.load(Ordering::Relaxed),
+            recent_stalls: self.recent_stalls.lock().iter().cloned().collect(),
+        }
+    }
+
+    /// Account for a finished poll
+    fn record(&self, clock: &TaskClock, elapsed: Duration) {
+        self.polls.record(elapsed);
+        if elapsed > self.budget {
+            self.over_budget.fetch_add(1, Ordering::Relaxed);
+        }
+        if elapsed >= self.blocking && !clock.reported.swap(true, Ordering::AcqRel) {
+            self.stall(clock.location, elapsed, false);
+        }
+    }
+
+    fn stall(&self, location: &'static Location<'static>, blocked: Duration, in_progress: bool) -> TaskStall {
+        let stall = TaskStall {
+            location: location.to_string(),
+            blocked_ms: blocked.as_millis() as u64,
+            in_progress,
+            at: Utc::now(),
+        };
+        if in_progress {
+            warn!("Task spawned at {} has blocked its worker thread for {}ms so far", stall.location, stall.blocked_ms);
+        } else {
+            warn!("Task spawned at {} blocked its worker thread for {}ms", stall.location, stall.blocked_ms);
+        }
+        self.stalls.fetch_add(1, Ordering::Relaxed);
+        let mut recent = self.recent_stalls.lock();
+        if recent.len() == RECENT_STALLS {
+            recent.pop_front();
+        }
+        recent.push_back(stall.clone());
+        stall
+    }
+}
+
+/// The watchdog thread: checks for blocking polls every interval while the
+/// monitor is on, parks while it is off, and ends with the monitor
+fn watch(monitor: Weak<PollMonitor>) {
+    while let Some(current) = monitor.upgrade() {
+        let interval = current.watchdog_interval;
+        if current.is_active() {
+            current.check();
+            drop(current);
+            std::thread::sleep(interval);
+        } else {
+            drop(current);
+            std::thread::park_timeout(interval.max(Duration::from_secs(1)));
+        }
+    }
+}
+
+/// A task spawned while the monitor was on
+struct Monitored<F> {
+    monitor: Arc<PollMonitor>,
+    id: u64,
+    clock: Arc<TaskClock>,
+    future: Pin<Box<F>>,
+}
+
+impl<F: Future> Future for Monitored<F> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<F::Output> {
+        let this = self.get_mut();
+        if !this.monitor.is_active() {
+            return this.future.as_mut().poll(cx);
+        }
+        let started = Instant::now();
+        this.clock.reported.store(false, Ordering::Release);
+        let since = started.saturating_duration_since(this.monitor.epoch).as_nanos() as u64 + 1;
+        this.clock.polling_since.store(since, Ordering::Release);
+        let result = this.future.as_mut().poll(cx);
+        this.clock.polling_since.store(0, Ordering::Release);
+        this.monitor.record(&this.clock, started.elapsed());
+        result
+    }
+}
+
+impl<F> Drop for Monitored<F> {
+    fn drop(&mut self) {
+        self.monitor.tasks.remove(&self.id);
+    }
+}
+
+/// Profiling switched off or on over the API
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProfilingSwitch {
+    pub active: bool,
+    /// Who switched, when known
+    pub by: Option<String>,
+    pub at: DateTime<Utc>,
+}
+
+/// Profiling as reported by `GET /api/v1/admin/profiling`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProfilingStatus {
+    pub active: bool,
+    /// A CPU profile is being taken
+    pub profiling_cpu: bool,
+    pub frequency_hz: i32,
+    pub max_profile_secs: u64,
+    pub poll_budget_ms: u64,
+    pub blocking_poll_ms: u64,
+    pub polls: PollStats,
+    pub last_switch: Option<ProfilingSwitch>,
+}
+
+/// CPU profiles and the poll monitor, and the admin switch of both
+pub struct Profiler {
+    config: ProfilingConfig,
+    polls: Arc<PollMonitor>,
+    last_switch: RwLock<Option<ProfilingSwitch>>,
+}
+
+impl Profiler {
+    pub fn new(config: ProfilingConfig) -> Self {
+        if config.enabled {
+            info!(
+                "Profiling on: poll budget {}ms, blocking polls from {}ms",
+                config.poll_budget_ms, config.blocking_poll_ms
+            );
+        }
+        Self {
+            polls: PollMonitor::new(&config),
+            config,
+            last_switch: RwLock::new(None),
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.polls.is_active()
+    }
+
+    /// The monitor signal tasks are spawned through
+    pub fn polls(&self) -> Arc<PollMonitor> {
+        self.polls.clone()
+    }
+
+    /// The admin switch. Tasks spawned while profiling was off stay
+    /// unmonitored; a CPU profile being taken finishes.
+    pub fn set_active(&self, active: bool, actor: Option<&str>) -> ProfilingStatus {
+        self.polls.set_active(active);
+        *self.last_switch.write() = Some(ProfilingSwitch {
+            active,
+            by: actor.map(str::to_string),
+            at: Utc::now(),
+        });
+        info!("Profiling switched {} (by {})", if active { "on" } else { "off" }, actor.unwrap_or("unknown"));
+        self.status()
+    }
+
+    pub fn status(&self) -> ProfilingStatus {
+        ProfilingStatus {
+            active: self.is_active(),
+            profiling_cpu: PROFILING_CPU.load(Ordering::SeqCst),
+            frequency_hz: self.config.frequency_hz,
+            max_profile_secs: self.config.max_profile_secs,
+            poll_budget_ms: self.config.poll_budget_ms,
+            blocking_poll_ms: self.config.blocking_poll_ms,
+            polls: self.polls.stats(),
+            last_switch: self.last_switch.read().clone(),
+        }
+    }
+
+    /// Sample the CPU for `seconds`, 30 as with Go's pprof when unset, and
+    /// encode the samples as a pprof protobuf, e.g. for `go tool pprof`
+    pub async fn cpu_profile(&self, seconds: Option<u64>) -> ServerResult<Vec<u8>> {
+        let seconds = seconds.unwrap_or(DEFAULT_PROFILE_SECS.min(self.config.max_profile_secs));
+        if !self.is_active() {
+            return Err(ServerError::NotFound("Profiling is not enabled".to_string()));
+        }
+        if seconds == 0 || seconds > self.config.max_profile_secs {
+            return Err(ServerError::InvalidInput(format!(
+                "seconds must be between 1 and {}",
+                self.config.max_profile_secs
+            )));
+        }
+        if PROFILING_CPU.swap(true, Ordering::SeqCst) {
+            return Err(ServerError::LimitExceeded("A CPU profile is being taken already".to_string()));
+        }
+        let frequency = self.config.frequency_hz;
+        // The sampling thread sleeps through the profile, off the executor
+        let profile = tokio::task::spawn_blocking(move || {
+            let result = sample_cpu(frequency, Duration::from_secs(seconds));
+            PROFILING_CPU.store(false, Ordering::SeqCst);
+            result
+        })
+        .await
+        .map_err(|e| ServerError::Internal(format!("CPU profile failed: {}", e)))?;
+        profile.map_err(|e| ServerError::Internal(format!("CPU profile failed: {}", e)))
+    }
+}
+
+fn sample_cpu(frequency: i32, duration: Duration) -> std::result::Result<Vec<u8>, pprof::Error> {
+    use pprof::protos::Message;
+
+    let guard = pprof::ProfilerGuardBuilder::default()
+        .frequency(frequency)
+        .blocklist(&["libc", "libgcc", "pthread", "vdso"])
+        .build()?;
+    std::thread::sleep(duration);
+    let profile = guard.report().build()?.pprof()?;
+    let mut encoded = Vec::with_capacity(profile.encoded_len());
+    profile.encode(&mut encoded).expect("a Vec grows to fit the profile");
+    Ok(encoded)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
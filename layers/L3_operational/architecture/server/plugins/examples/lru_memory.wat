@@ -0,0 +1,198 @@
+;; Reference memory provider plugin: an LRU store in the plugin's own memory
+;;
+;; Keeps up to 16 values of at most 8 KiB under keys of at most 64 bytes,
+;; evicting the least recently used value when full. Gets and sets count as
+;; uses; searches do not. See `plugins/memory_backend.rs` for the exports a
+;; memory provider implements.
+;;
+;; Layout: a 16-byte table entry per slot (used, key length, value length,
+;; last use) at 1024, keys at 2048, values at 4096, the argument buffer at
+;; 135168 and search results at 151552.
+(module
+  (memory (export "memory") 5)
+
+  (global $slots i32 (i32.const 16))
+  (global $key_max i32 (i32.const 64))
+  (global $value_max i32 (i32.const 8192))
+  (global $table i32 (i32.const 1024))
+  (global $keys i32 (i32.const 2048))
+  (global $values i32 (i32.const 4096))
+  (global $input i32 (i32.const 135168))
+  (global $input_max i32 (i32.const 16384))
+  (global $output i32 (i32.const 151552))
+  ;; Ticks on every use; a slot's last use is the tick it was last used at
+  (global $clock (mut i32) (i32.const 0))
+
+  (func (export "on_activate"))
+
+  ;; The argument buffer, shared by every call
+  (func (export "allocate") (param $len i32) (result i32)
+    (if (result i32) (i32.gt_u (local.get $len) (global.get $input_max))
+      (then (i32.const 0))
+      (else (global.get $input))))
+
+  (func $meta (param $slot i32) (result i32)
+    (i32.add (global.get $table) (i32.shl (local.get $slot) (i32.const 4))))
+
+  (func $key_at (param $slot i32) (result i32)
+    (i32.add (global.get $keys) (i32.mul (local.get $slot) (global.get $key_max))))
+
+  (func $value_at (param $slot i32) (result i32)
+    (i32.add (global.get $values) (i32.mul (local.get $slot) (global.get $value_max))))
+
+  (func $pack (param $ptr i32) (param $len i32) (result i64)
+    (i64.or
+      (i64.shl (i64.extend_i32_u (local.get $ptr)) (i64.const 32))
+      (i64.extend_i32_u (local.get $len))))
+
+  (func $equal (param $a i32) (param $b i32) (param $len i32) (result i32)
+    (local $i i32)
+    (block $done
+      (loop $next
+        (br_if $done (i32.ge_u (local.get $i) (local.get $len)))
+        (if (i32.ne (i32.load8_u (i32.add (local.get $a) (local.get $i)))
+                    (i32.load8_u (i32.add (local.get $b) (local.get $i))))
+          (then (return (i32.const 0))))
+        (local.set $i (i32.add (local.get $i) (i32.const 1)))
+        (br $next)))
+    (i32.const 1))
+
+  (func $contains (param $haystack i32) (param $haystack_len i32) (param $needle i32) (param $needle_len i32) (result i32)
+    (local $i i32)
+    (if (i32.gt_u (local.get $needle_len) (local.get $haystack_len))
+      (then (return (i32.const 0))))
+    (block $done
+      (loop $next
+        (br_if $done (i32.gt_u (local.get $i) (i32.sub (local.get $haystack_len) (local.get $needle_len))))
+        (if (call $equal (i32.add (local.get $haystack) (local.get $i)) (local.get $needle) (local.get $needle_len))
+          (then (return (i32.const 1))))
+        (local.set $i (i32.add (local.get $i) (i32.const 1)))
+        (br $next)))
+    (i32.const 0))
+
+  ;; Slot holding the key, or -1
+  (func $find (param $key i32) (param $len i32) (result i32)
+    (local $slot i32)
+    (local $meta i32)
+    (block $done
+      (loop $next
+        (br_if $done (i32.ge_u (local.get $slot) (global.get $slots)))
+        (local.set $meta (call $meta (local.get $slot)))
+        (if (i32.and
+              (i32.load (local.get $meta))
+              (i32.and
+                (i32.eq (i32.load offset=4 (local.get $meta)) (local.get $len))
+                (call $equal (call $key_at (local.get $slot)) (local.get $key) (local.get $len))))
+          (then (return (local.get $slot))))
+        (local.set $slot (i32.add (local.get $slot) (i32.const 1)))
+        (br $next)))
+    (i32.const -1))
+
+  (func $touch (param $slot i32)
+    (global.set $clock (i32.add (global.get $clock) (i32.const 1)))
+    (i32.store offset=12 (call $meta (local.get $slot)) (global.get $clock)))
+
+  ;; A free slot, or else the least recently used one
+  (func $victim (result i32)
+    (local $slot i32)
+    (local $meta i32)
+    (local $oldest i32)
+    (block $done
+      (loop $next
+        (br_if $done (i32.ge_u (local.get $slot) (global.get $slots)))
+        (local.set $meta (call $meta (local.get $slot)))
+        (if (i32.eqz (i32.load (local.get $meta)))
+          (then (return (local.get $slot))))
+        (if (i32.lt_u (i32.load offset=12 (local.get $meta))
+                      (i32.load offset=12 (call $meta (local.get $oldest))))
+          (then (local.set $oldest (local.get $slot))))
+        (local.set $slot (i32.add (local.get $slot) (i32.const 1)))
+        (br $next)))
+    (local.get $oldest))
+
+  (func (export "memory_get") (param $ptr i32) (param $key_len i32) (result i64)
+    (local $slot i32)
+    (local.set $slot (call $find (local.get $ptr) (local.get $key_len)))
+    (if (i32.lt_s (local.get $slot) (i32.const 0))
+      (then (return (i64.const -1))))
+    (call $touch (local.get $slot))
+    (call $pack
+      (call $value_at (local.get $slot))
+      (i32.load offset=8 (call $meta (local.get $slot)))))
+
+  ;; -1 for a key too long, -2 for a value too large
+  (func (export "memory_set") (param $ptr i32) (param $key_len i32) (param $value_len i32) (result i32)
+    (local $slot i32)
+    (local $meta i32)
+    (if (i32.gt_u (local.get $key_len) (global.get $key_max))
+      (then (return (i32.const -1))))
+    (if (i32.gt_u (local.get $value_len) (global.get $value_max))
+      (then (return (i32.const -2))))
+    (local.set $slot (call $find (local.get $ptr) (local.get $key_len)))
+    (if (i32.lt_s (local.get $slot) (i32.const 0))
+      (then (local.set $slot (call $victim))))
+    (local.set $meta (call $meta (local.get $slot)))
+    (memory.copy (call $key_at (local.get $slot)) (local.get $ptr) (local.get $key_len))
+    (memory.copy
+      (call $value_at (local.get $slot))
+      (i32.add (local.get $ptr) (local.get $key_len))
+      (local.get $value_len))
+    (i32.store (local.get $meta) (i32.const 1))
+    (i32.store offset=4 (local.get $meta) (local.get $key_len))
+    (i32.store offset=8 (local.get $meta) (local.get $value_len))
+    (call $touch (local.get $slot))
+    (i32.const 0))
+
+  (func (export "memory_delete") (param $ptr i32) (param $key_len i32) (result i32)
+    (local $slot i32)
+    (local.set $slot (call $find (local.get $ptr) (local.get $key_len)))
+    (if (i32.lt_s (local.get $slot) (i32.const 0))
+      (then (return (i32.const 0))))
+    (i32.store (call $meta (local.get $slot)) (i32.const 0))
+    (i32.const 1))
+
+  ;; Visits the slots from the most recently used, copying each value that
+  ;; contains the query to the results, followed by a newline
+  (func (export "memory_search") (param $ptr i32) (param $query_len i32) (param $limit i32) (result i64)
+    (local $before i32)
+    (local $best i32)
+    (local $best_used i32)
+    (local $slot i32)
+    (local $meta i32)
+    (local $len i32)
+    (local $found i32)
+    (local $end i32)
+    (local.set $before (i32.const -1))
+    (local.set $end (global.get $output))
+    (block $done
+      (loop $next
+        ;; The most recently used slot not visited yet
+        (local.set $best (i32.const -1))
+        (local.set $best_used (i32.const 0))
+        (local.set $slot (i32.const 0))
+        (block $scanned
+          (loop $scan
+            (br_if $scanned (i32.ge_u (local.get $slot) (global.get $slots)))
+            (local.set $meta (call $meta (local.get $slot)))
+            (if (i32.and
+                  (i32.load (local.get $meta))
+                  (i32.and
+                    (i32.lt_u (i32.load offset=12 (local.get $meta)) (local.get $before))
+                    (i32.ge_u (i32.load offset=12 (local.get $meta)) (local.get $best_used))))
+              (then
+                (local.set $best (local.get $slot))
+                (local.set $best_used (i32.load offset=12 (local.get $meta)))))
+            (local.set $slot (i32.add (local.get $slot) (i32.const 1)))
+            (br $scan)))
+        (br_if $done (i32.lt_s (local.get $best) (i32.const 0)))
+        (local.set $before (local.get $best_used))
+        (local.set $len (i32.load offset=8 (call $meta (local.get $best))))
+        (if (call $contains (call $value_at (local.get $best)) (local.get $len) (local.get $ptr) (local.get $query_len))
+          (then
+            (memory.copy (local.get $end) (call $value_at (local.get $best)) (local.get $len))
+            (i32.store8 (i32.add (local.get $end) (local.get $len)) (i32.const 10))
+            (local.set $end (i32.add (local.get $end) (i32.add (local.get $len) (i32.const 1))))
+            (local.set $found (i32.add (local.get $found) (i32.const 1)))
+            (br_if $done (i32.eq (local.get $found) (local.get $limit)))))
+        (br $next)))
+    (call $pack (global.get $output) (i32.sub (local.get $end) (global.get $output)))))
@@ -8,9 +8,14 @@ use uuid::Uuid;
 use super::{
     api::*,
     loader::{PluginLoader, LoadedPlugin},
+    memory_backend::PluginBackend,
     registry::PluginRegistry,
-    runtime::{WasmRuntime, RuntimeConfig},
+    runtime::{BudgetExceeded, WasmRuntime, RuntimeConfig},
 };
+use hal9_core::config::MemoryNamespacesConfig;
+use hal9_core::memory::{MemoryNamespace, NamespacedMemory};
+use crate::chaos::ChaosEngine;
+use crate::metrics::Metrics;
 use crate::signal::Signal;
 
 // ============ Plugin State ============
@@ -47,6 +52,8 @@ pub struct PluginManager {
     plugins: Arc<DashMap<Uuid, Arc<RwLock<ManagedPlugin>>>>,
     capabilities: Arc<DashMap<String, Vec<Uuid>>>,
     config: PluginManagerConfig,
+    /// Corrupts plugin responses while a chaos injection says so
+    chaos: Option<Arc<ChaosEngine>>,
 }
 
 #[derive(Debug, Clone)]
@@ -71,10 +78,12 @@ impl Default for PluginManagerConfig {
 }
 
 impl PluginManager {
-    pub async fn new(config: PluginManagerConfig) -> Result<Self> {
-        // Create runtime
-        let runtime = Arc::new(WasmRuntime::new(RuntimeConfig::default())?)
-            
+    pub async fn new(config: PluginManagerConfig, metrics: Arc<Metrics>) -> Result<Self> {
+        // Create runtime, reporting plugin usage with the server's metrics
+        let mut runtime = WasmRuntime::new(RuntimeConfig::default())?;
+        runtime.set_metrics(metrics);
+        let runtime = Arc::new(runtime);
+        
         // Create loader
         let loader = Arc::new(PluginLoader::new(
             config.plugins_dir.clone(),
@@ -91,6 +100,7 @@ impl PluginManager {
             plugins: Arc::new(DashMap::new()),
             capabilities: Arc::new(DashMap::new()),
             config,
+            chaos: None,
         };
         
         // Auto-load plugins if enabled
@@ -101,6 +111,11 @@ impl PluginManager {
         Ok(manager)
     }
     
+    /// Set the engine whose injections may corrupt plugin responses
+    pub fn set_chaos(&mut self, chaos: Arc<ChaosEngine>) {
+        self.chaos = Some(chaos);
+    }
+    
     /// Load all plugins from the plugins directory
     pub async fn load_all_plugins(&self) -> Result<Vec<Uuid>> {
         let plugins = self.loader.scan_and_load_all().await?;
@@ -290,7 +305,67 @@ impl PluginManager {
             .unwrap_or_default()
     }
     
-    /// Process a signal through plugin neurons
+    /// Store the namespaces `config.backends` maps to plugins in those
+    /// plugins. A namespace whose plugin is not an active memory provider
+    /// stays read-only. Returns the backends attached.
+    pub async fn attach_memory_backends(
+        &self,
+        memory: &mut NamespacedMemory,
+        config: &MemoryNamespacesConfig,
+    ) -> Vec<Arc<PluginBackend>> {
+        let mut attached = Vec::new();
+        
+        for (name, backend_config) in &config.backends {
+            let namespace = match name.parse::<MemoryNamespace>() {
+                Ok(namespace) => namespace,
+                Err(e) => {
+                    tracing::error!("Ignoring memory backend: {}", e);
+                    continue;
+                }
+            };
+            let Some(plugin_id) = self.find_memory_provider(&backend_config.plugin).await else {
+                tracing::warn!(
+                    "Plugin {} is not an active memory provider, memory namespace {} stays read-only",
+                    backend_config.plugin, namespace
+                );
+                continue;
+            };
+            
+            let backend = Arc::new(PluginBackend::new(
+                self.runtime.clone(),
+                plugin_id.to_string(),
+                namespace.clone(),
+                backend_config.clone(),
+            ));
+            memory.set_backend(&namespace, backend.clone());
+            tracing::info!("Memory namespace {} is stored by plugin {}", namespace, backend_config.plugin);
+            attached.push(backend);
+        }
+        
+        attached
+    }
+    
+    /// Active plugin named `name` with the `MemoryProvider` capability
+    async fn find_memory_provider(&self, name: &str) -> Option<Uuid> {
+        let providers: Vec<Uuid> = self.capabilities.iter()
+            .filter(|entry| entry.key().starts_with("memory:"))
+            .flat_map(|entry| entry.value().clone())
+            .collect();
+        
+        for plugin_id in providers {
+            let Some(plugin_arc) = self.plugins.get(&plugin_id).map(|entry| entry.value().clone()) else {
+                continue;
+            };
+            let plugin = plugin_arc.read().await;
+            if plugin.loaded.metadata.name == name && plugin.state == PluginState::Active {
+                return Some(plugin_id);
+            }
+        }
+        None
+    }
+    
+    /// Process a signal through plugin neurons. A plugin terminated for
+    /// exceeding its budget fails the call, so the neuron sees why.
     pub async fn process_signal_through_plugins(
         &self,
         signal: &Signal,
@@ -321,10 +396,14 @@ impl PluginManager {
                 // Call plugin
                 match self.call_plugin_neuron(&plugin_id, plugin_signal).await {
                     Ok(result) => {
+                        let content = self.chaos.as_ref()
+                            .and_then(|chaos| chaos.corrupt_plugin_response(&plugin_id.to_string(), &result.content))
+                            .unwrap_or(result.content);
+                        
                         // Convert back to Signal
                         let output_signal = Signal {
                             id: result.id,
-                            content: result.content,
+                            content,
                             source: format!("plugin:{}", plugin_id),
                             target: signal.target.clone(),
                             signal_type: result.signal_type,
@@ -335,6 +414,11 @@ impl PluginManager {
                         };
                         results.push(output_signal);
                     }
+                    Err(e) if e.downcast_ref::<BudgetExceeded>().is_some() => {
+                        drop(plugin);
+                        plugin_arc.write().await.state = PluginState::Failed(e.to_string());
+                        return Err(e);
+                    }
                     Err(e) => {
                         tracing::error!("Plugin {} failed to process signal: {}", plugin_id, e);
                     }
@@ -0,0 +1,236 @@
+//! Memory namespaces stored by plugins
+//!
+//! A plugin with the `MemoryProvider` capability stores a namespace's
+//! entries when `memory.namespaces.backends` maps the namespace to it. Next
+//! to its `memory`, the plugin exports:
+//!
+//! - `allocate(len) -> ptr`: a buffer of `len` bytes for the arguments of
+//!   the next call, or 0 if it has none that large
+//! - `memory_get(ptr, key_len) -> i64`: the value stored under the key
+//! - `memory_set(ptr, key_len, value_len) -> i32`: store the value that
+//!   follows the key in the buffer; 0 on success, -2 for a value too large
+//!   for the plugin, any other negative number on failure
+//! - `memory_delete(ptr, key_len) -> i32`: 1 if the key was removed, 0 if
+//!   there was none
+//! - `memory_search(ptr, query_len, limit) -> i64`: the values containing
+//!   the query, most recently used first, each followed by a newline; at
+//!   most `limit` unless it is 0
+//!
+//! Values come back as `(ptr << 32) | len`, or -1 for none. Keys are entry
+//! IDs and values entries serialized as JSON. `examples/lru_memory.wat` is
+//! a reference plugin keeping entries in its own memory.
+//!
+//! A call that fails, times out or answers something unreadable turns the
+//! namespace read-only: writes are refused from then on, while reads keep
+//! being tried. Other namespaces carry on.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::anyhow;
+use async_trait::async_trait;
+use parking_lot::Mutex;
+use tracing::warn;
+use uuid::Uuid;
+use wasmtime::Val;
+
+use hal9_core::config::NamespaceBackendConfig;
+use hal9_core::memory::{MemoryBackend, MemoryEntry, MemoryNamespace};
+use hal9_core::{Error, Result};
+
+use super::runtime::WasmRuntime;
+
+/// `memory_set` status for a value too large for the plugin
+const TOO_LARGE: i32 = -2;
+
+/// What a call into the plugin answered
+enum Answer {
+    Status(i32),
+    Value(Option<Vec<u8>>),
+    /// The plugin had no buffer large enough for the arguments
+    NoBuffer,
+}
+
+/// A namespace's entries, stored by a memory provider plugin
+pub struct PluginBackend {
+    runtime: Arc<WasmRuntime>,
+    plugin_id: String,
+    namespace: MemoryNamespace,
+    config: NamespaceBackendConfig,
+    /// One call at a time, so a call's arguments stay in their buffer
+    calls: tokio::sync::Mutex<()>,
+    /// Why the namespace turned read-only
+    failure: Mutex<Option<String>>,
+}
+
+impl PluginBackend {
+    /// Store `namespace` in the plugin loaded into `runtime` as `plugin_id`
+    pub fn new(
+        runtime: Arc<WasmRuntime>,
+        plugin_id: impl Into<String>,
+        namespace: MemoryNamespace,
+        config: NamespaceBackendConfig,
+    ) -> Self {
+        Self {
+            runtime,
+            plugin_id: plugin_id.into(),
+            namespace,
+            config,
+            calls: tokio::sync::Mutex::new(()),
+            failure: Mutex::new(None),
+        }
+    }
+
+    /// Why the namespace turned read-only, if it did
+    pub fn failure(&self) -> Option<String> {
+        self.failure.lock().clone()
+    }
+
+    fn writable(&self) -> Result<()> {
+        match &*self.failure.lock() {
+            Some(reason) => Err(Error::ReadOnly(format!(
+                "memory namespace {} is read-only since plugin {} failed: {}",
+                self.namespace, self.config.plugin, reason
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    /// Turn the namespace read-only after `function` failed
+    fn fail(&self, function: &str, error: anyhow::Error) -> Error {
+        let reason = format!("{}: {:#}", function, error);
+        let mut failure = self.failure.lock();
+        if failure.is_none() {
+            warn!(
+                "Plugin {} failed for memory namespace {}, which is read-only from now on: {}",
+                self.config.plugin, self.namespace, reason
+            );
+            *failure = Some(reason.clone());
+        }
+        Error::Storage(format!(
+            "plugin {} failed for memory namespace {}: {}",
+            self.config.plugin, self.namespace, reason
+        ))
+    }
+
+    /// Call `function` with `args` in the plugin's buffer, followed by
+    /// `params`, within the timeout
+    async fn call(&self, function: &str, args: &[u8], params: &[i32]) -> Result<Answer> {
+        let timeout = Duration::from_millis(self.config.timeout_ms);
+        let answer = match tokio::time::timeout(timeout, self.exchange(function, args, params)).await {
+            Ok(answer) => answer,
+            Err(_) => Err(anyhow!("timed out after {} ms", self.config.timeout_ms)),
+        };
+        answer.map_err(|e| self.fail(function, e))
+    }
+
+    async fn exchange(&self, function: &str, args: &[u8], params: &[i32]) -> anyhow::Result<Answer> {
+        let _call = self.calls.lock().await;
+        let ptr = self.runtime.call_function(&self.plugin_id, "allocate", &[Val::I32(args.len() as i32)]).await?
+            .first()
+            .and_then(Val::i32)
+            .ok_or_else(|| anyhow!("allocate answered no pointer"))?;
+        if ptr == 0 {
+            return Ok(Answer::NoBuffer);
+        }
+        self.runtime.write_memory(&self.plugin_id, ptr as u32 as usize, args).await?;
+
+        let mut call_args = vec![Val::I32(ptr)];
+        call_args.extend(params.iter().map(|param| Val::I32(*param)));
+        let results = self.runtime.call_function(&self.plugin_id, function, &call_args).await?;
+        match results.first() {
+            Some(Val::I32(status)) => Ok(Answer::Status(*status)),
+            Some(Val::I64(-1)) => Ok(Answer::Value(None)),
+            Some(Val::I64(packed)) => {
+                let packed = *packed as u64;
+                let (ptr, len) = ((packed >> 32) as usize, (packed & 0xffff_ffff) as usize);
+                if len > self.config.max_response_bytes {
+                    return Err(anyhow!(
+                        "answered {} bytes, over the limit of {}",
+                        len, self.config.max_response_bytes
+                    ));
+                }
+                Ok(Answer::Value(Some(self.runtime.read_memory(&self.plugin_id, ptr, len).await?)))
+            }
+            other => Err(anyhow!("answered {:?}", other)),
+        }
+    }
+
+    async fn status(&self, function: &str, args: &[u8], params: &[i32]) -> Result<i32> {
+        match self.call(function, args, params).await? {
+            Answer::Status(status) => Ok(status),
+            Answer::NoBuffer => Err(self.too_large(args.len())),
+            Answer::Value(_) => Err(self.fail(function, anyhow!("answered a value instead of a status"))),
+        }
+    }
+
+    async fn value(&self, function: &str, args: &[u8], params: &[i32]) -> Result<Option<Vec<u8>>> {
+        match self.call(function, args, params).await? {
+            Answer::Value(value) => Ok(value),
+            Answer::NoBuffer => Err(self.too_large(args.len())),
+            Answer::Status(_) => Err(self.fail(function, anyhow!("answered a status instead of a value"))),
+        }
+    }
+
+    fn too_large(&self, bytes: usize) -> Error {
+        Error::QuotaExceeded(format!(
+            "plugin {} cannot take {} bytes for memory namespace {}",
+            self.config.plugin, bytes, self.namespace
+        ))
+    }
+
+    fn decode(&self, function: &str, value: &[u8]) -> Result<MemoryEntry> {
+        serde_json::from_slice(value).map_err(|e| self.fail(function, e.into()))
+    }
+}
+
+#[async_trait]
+impl MemoryBackend for PluginBackend {
+    async fn get(&self, id: Uuid) -> Result<Option<MemoryEntry>> {
+        let key = id.to_string();
+        match self.value("memory_get", key.as_bytes(), &[key.len() as i32]).await? {
+            Some(value) => self.decode("memory_get", &value).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    async fn set(&self, entry: MemoryEntry) -> Result<()> {
+        self.writable()?;
+        let value = serde_json::to_vec(&entry)?;
+        if value.len() > self.config.max_value_bytes {
+            return Err(Error::QuotaExceeded(format!(
+                "memory entry of {} bytes is over the limit of {} for namespace {}",
+                value.len(), self.config.max_value_bytes, self.namespace
+            )));
+        }
+
+        let key = entry.id.to_string();
+        let mut args = key.clone().into_bytes();
+        args.extend_from_slice(&value);
+        match self.status("memory_set", &args, &[key.len() as i32, value.len() as i32]).await? {
+            0 => Ok(()),
+            TOO_LARGE => Err(self.too_large(value.len())),
+            status => Err(self.fail("memory_set", anyhow!("failed with status {}", status))),
+        }
+    }
+
+    async fn delete(&self, id: Uuid) -> Result<bool> {
+        self.writable()?;
+        let key = id.to_string();
+        match self.status("memory_delete", key.as_bytes(), &[key.len() as i32]).await? {
+            0 => Ok(false),
+            1 => Ok(true),
+            status => Err(self.fail("memory_delete", anyhow!("failed with status {}", status))),
+        }
+    }
+
+    async fn search(&self, query: &str, limit: usize) -> Result<Vec<MemoryEntry>> {
+        let limit = i32::try_from(limit).unwrap_or(i32::MAX);
+        let values = self.value("memory_search", query.as_bytes(), &[query.len() as i32, limit]).await?
+            .unwrap_or_default();
+        values.split(|byte| *byte == b'\n')
+            .filter(|value| !value.is_empty())
+            .map(|value| self.decode("memory_search", value))
+            .collect()
+    }
+}
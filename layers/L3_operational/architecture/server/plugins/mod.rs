@@ -1,6 +1,7 @@
 pub mod api;
 pub mod loader;
 pub mod manager;
+pub mod memory_backend;
 pub mod runtime;
 pub mod sandbox;
 pub mod registry;
@@ -9,7 +10,8 @@ pub mod sdk;
 pub use api::{PluginApi, PluginMetadata, PluginCapability};
 pub use loader::{PluginLoader, LoadedPlugin};
 pub use manager::{PluginManager, PluginError};
-pub use runtime::{WasmRuntime, RuntimeConfig};
+pub use memory_backend::PluginBackend;
+pub use runtime::{BudgetExceeded, WasmRuntime, RuntimeConfig};
 pub use sandbox::{SecurityPolicy, ResourceLimits};
 pub use registry::{PluginRegistry, PluginPackage};
 
@@ -1,13 +1,17 @@
 use anyhow::{Context, Result};
 use std::collections::HashMap;
 use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tokio::sync::RwLock;
 use wasmtime::*;
 use wasmtime_wasi::{WasiCtx, WasiCtxBuilder};
 
 use super::api::*;
 use super::sandbox::SecurityPolicy;
+use crate::logging::plugin_span;
+use crate::metrics::{Metrics, PluginBudget, PluginCallOutcome};
 
 // ============ Runtime Configuration ============
 
@@ -15,9 +19,14 @@ use super::sandbox::SecurityPolicy;
 pub struct RuntimeConfig {
     pub max_memory_pages: u32,
     pub enable_fuel: bool,
+    /// Fuel for a plugin's `_initialize`
     pub initial_fuel: u64,
+    /// Fuel each call into a plugin may burn before it is terminated
+    pub fuel_per_call: u64,
     pub enable_epoch_interruption: bool,
     pub epoch_deadline_ms: u64,
+    /// Table elements a plugin may grow to
+    pub max_table_elements: u32,
     pub enable_cache: bool,
     pub cache_dir: Option<String>,
 }
@@ -28,8 +37,10 @@ impl Default for RuntimeConfig {
             max_memory_pages: 1024, // 64MB max memory
             enable_fuel: true,
             initial_fuel: 1_000_000_000,
+            fuel_per_call: 1_000_000_000,
             enable_epoch_interruption: true,
             epoch_deadline_ms: 5000,
+            max_table_elements: 10_000,
             enable_cache: true,
             cache_dir: Some("/tmp/hal9-wasm-cache".to_string()),
         }
@@ -42,6 +53,43 @@ pub struct WasmRuntime {
     engine: Engine,
     config: RuntimeConfig,
     instances: Arc<RwLock<HashMap<String, PluginInstance>>>,
+    metrics: Option<Arc<Metrics>>,
+    _epoch_ticker: Option<EpochTicker>,
+}
+
+/// A plugin call terminated for exceeding its budget. The plugin is
+/// unloaded; the host and other plugins carry on.
+#[derive(Debug, thiserror::Error)]
+#[error("Plugin {plugin} exceeded its {} budget and was terminated", budget.as_str())]
+pub struct BudgetExceeded {
+    pub plugin: String,
+    pub budget: PluginBudget,
+}
+
+/// Advances the engine epoch every millisecond, so epoch deadlines are in
+/// milliseconds
+struct EpochTicker {
+    stop: Arc<AtomicBool>,
+}
+
+impl EpochTicker {
+    fn start(engine: Engine) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let stopped = stop.clone();
+        std::thread::spawn(move || {
+            while !stopped.load(Ordering::Relaxed) {
+                std::thread::sleep(Duration::from_millis(1));
+                engine.increment_epoch();
+            }
+        });
+        Self { stop }
+    }
+}
+
+impl Drop for EpochTicker {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
 }
 
 struct PluginInstance {
@@ -56,6 +104,40 @@ struct PluginStore {
     host_functions: HostFunctions,
     plugin_context: PluginContext,
     fuel_consumed: u64,
+    /// Plugin name, as metrics label it
+    name: String,
+    limiter: PluginLimiter,
+    metrics: Option<Arc<Metrics>>,
+}
+
+/// Holds a plugin to its memory and table budget, and tracks their size
+struct PluginLimiter {
+    max_memory_bytes: usize,
+    max_table_elements: u32,
+    memory_bytes: usize,
+    table_elements: u32,
+    /// Set when the plugin tried to grow past its budget
+    exceeded: Option<PluginBudget>,
+}
+
+impl ResourceLimiter for PluginLimiter {
+    fn memory_growing(&mut self, _current: usize, desired: usize, _maximum: Option<usize>) -> bool {
+        if desired > self.max_memory_bytes {
+            self.exceeded = Some(PluginBudget::Memory);
+            return false;
+        }
+        self.memory_bytes = desired;
+        true
+    }
+    
+    fn table_growing(&mut self, _current: u32, desired: u32, _maximum: Option<u32>) -> bool {
+        if desired > self.max_table_elements {
+            self.exceeded = Some(PluginBudget::Memory);
+            return false;
+        }
+        self.table_elements = desired;
+        true
+    }
 }
 
 struct HostFunctions {
@@ -98,14 +180,22 @@ impl WasmRuntime {
         engine_config.wasm_multi_value(true);
         
         let engine = Engine::new(&engine_config)?;
+        let epoch_ticker = config.enable_epoch_interruption.then(|| EpochTicker::start(engine.clone()));
         
         Ok(Self {
             engine,
             config,
             instances: Arc::new(RwLock::new(HashMap::new())),
+            metrics: None,
+            _epoch_ticker: epoch_ticker,
         })
     }
     
+    /// Record plugin calls, host function calls and resource usage
+    pub fn set_metrics(&mut self, metrics: Arc<Metrics>) {
+        self.metrics = Some(metrics);
+    }
+    
     /// Load a plugin from WASM bytes
     pub async fn load_plugin(
         &self,
@@ -122,6 +212,10 @@ impl WasmRuntime {
         // Create WASI context based on security policy
         let wasi_ctx = self.create_wasi_context(&context, security_policy)?;
         
+        // Memory budget: the plugin's own limit, within the runtime's
+        let max_memory_bytes = context.resource_limits.max_memory_bytes
+            .min(self.config.max_memory_pages as u64 * 65536) as usize;
+        
         // Create store with plugin context
         let mut store = Store::new(
             &self.engine,
@@ -130,8 +224,18 @@ impl WasmRuntime {
                 host_functions: HostFunctions::default(),
                 plugin_context: context,
                 fuel_consumed: 0,
+                name: metadata.name.clone(),
+                limiter: PluginLimiter {
+                    max_memory_bytes,
+                    max_table_elements: self.config.max_table_elements,
+                    memory_bytes: 0,
+                    table_elements: 0,
+                    exceeded: None,
+                },
+                metrics: self.metrics.clone(),
             },
         );
+        store.limiter(|state| &mut state.limiter);
         
         // Add initial fuel
         if self.config.enable_fuel {
@@ -183,8 +287,17 @@ impl WasmRuntime {
         let instance = instances.get_mut(plugin_id)
             .ok_or_else(|| anyhow::anyhow!("Plugin not found: {}", plugin_id))?;
         
-        let func = instance.exports.get(function_name)
+        let func = *instance.exports.get(function_name)
             .ok_or_else(|| anyhow::anyhow!("Function not found: {}", function_name))?;
+        let plugin = instance.metadata.name.clone();
+        
+        // Every call gets the full fuel budget, whatever earlier calls left
+        if self.config.enable_fuel {
+            let remaining = instance.store.consume_fuel(0)?;
+            if remaining < self.config.fuel_per_call {
+                instance.store.add_fuel(self.config.fuel_per_call - remaining)?;
+            }
+        }
         
         // Set epoch deadline
         if self.config.enable_epoch_interruption {
@@ -195,9 +308,42 @@ impl WasmRuntime {
         let func_ty = func.ty(&instance.store);
         let mut results = vec![Val::I32(0); func_ty.results().len()];
         
-        // Call the function
-        func.call(&mut instance.store, args, &mut results)
-            .context("Function call failed")?;
+        // Call the function, inside the calling neuron's span
+        let span = plugin_span(&plugin, function_name);
+        let _enter = span.enter();
+        let started = Instant::now();
+        let result = func.call(&mut instance.store, args, &mut results);
+        let elapsed = started.elapsed();
+        
+        // A refused memory.grow need not trap, so the limiter is asked too
+        let exhausted = instance.store.data().limiter.exceeded.or_else(|| {
+            let trap = result.as_ref().err()?.downcast_ref::<Trap>()?;
+            matches!(trap, Trap::OutOfFuel | Trap::Interrupt).then_some(PluginBudget::Cpu)
+        });
+        let outcome = match (&result, exhausted) {
+            (_, Some(budget)) => PluginCallOutcome::Exhausted(budget),
+            (Ok(()), None) => PluginCallOutcome::Ok,
+            (Err(_), None) => PluginCallOutcome::Error,
+        };
+        span.record("duration_ms", elapsed.as_secs_f64() * 1000.0);
+        span.record("outcome", tracing::field::debug(outcome));
+        
+        if let Some(metrics) = &self.metrics {
+            let limiter = &instance.store.data().limiter;
+            metrics.record_plugin_call(&plugin, function_name, elapsed, outcome);
+            metrics.set_plugin_usage(&plugin, limiter.memory_bytes as u64, limiter.table_elements as u64);
+        }
+        
+        if let Some(budget) = exhausted {
+            // Trapped mid-call, its state cannot be trusted: drop the
+            // instance without running `_cleanup`
+            instances.remove(plugin_id);
+            if let Some(metrics) = &self.metrics {
+                metrics.set_plugin_usage(&plugin, 0, 0);
+            }
+            tracing::warn!("Plugin {} exceeded its {} budget in {}, unloaded", plugin, budget.as_str(), function_name);
+            return Err(BudgetExceeded { plugin, budget }.into());
+        }
         
         // Check fuel consumption
         if self.config.enable_fuel {
@@ -206,9 +352,35 @@ impl WasmRuntime {
             instance.store.data_mut().fuel_consumed = fuel_consumed;
         }
         
+        result.context("Function call failed")?;
         Ok(results)
     }
     
+    /// Copy `bytes` into a plugin's linear memory at `offset`, e.g. into a
+    /// buffer its `allocate` export returned
+    pub async fn write_memory(&self, plugin_id: &str, offset: usize, bytes: &[u8]) -> Result<()> {
+        let mut instances = self.instances.write().await;
+        let instance = instances.get_mut(plugin_id)
+            .ok_or_else(|| anyhow::anyhow!("Plugin not found: {}", plugin_id))?;
+        let memory = instance.instance.get_memory(&mut instance.store, "memory")
+            .ok_or_else(|| anyhow::anyhow!("Plugin {} exports no memory", plugin_id))?;
+        memory.write(&mut instance.store, offset, bytes)
+            .map_err(|_| anyhow::anyhow!("Plugin {} memory write out of bounds", plugin_id))
+    }
+    
+    /// Copy `len` bytes out of a plugin's linear memory at `offset`
+    pub async fn read_memory(&self, plugin_id: &str, offset: usize, len: usize) -> Result<Vec<u8>> {
+        let mut instances = self.instances.write().await;
+        let instance = instances.get_mut(plugin_id)
+            .ok_or_else(|| anyhow::anyhow!("Plugin not found: {}", plugin_id))?;
+        let memory = instance.instance.get_memory(&mut instance.store, "memory")
+            .ok_or_else(|| anyhow::anyhow!("Plugin {} exports no memory", plugin_id))?;
+        let mut bytes = vec![0; len];
+        memory.read(&instance.store, offset, &mut bytes)
+            .map_err(|_| anyhow::anyhow!("Plugin {} memory read out of bounds", plugin_id))?;
+        Ok(bytes)
+    }
+    
     /// Unload a plugin
     pub async fn unload_plugin(&self, plugin_id: &str) -> Result<()> {
         let mut instances = self.instances.write().await;
@@ -280,31 +452,37 @@ impl WasmRuntime {
     fn link_host_functions(&self, linker: &mut Linker<PluginStore>) -> Result<()> {
         // Logging functions
         linker.func_wrap("hal9", "log_debug", |mut caller: Caller<'_, PluginStore>, ptr: i32, len: i32| {
+            record_host_call(&caller, "log_debug");
             let message = read_string_from_memory(&mut caller, ptr, len)?;
             tracing::debug!("[Plugin] {}", message);
             Ok(())
         })?;
         
         linker.func_wrap("hal9", "log_info", |mut caller: Caller<'_, PluginStore>, ptr: i32, len: i32| {
+            record_host_call(&caller, "log_info");
             let message = read_string_from_memory(&mut caller, ptr, len)?;
             tracing::info!("[Plugin] {}", message);
             Ok(())
         })?;
         
         linker.func_wrap("hal9", "log_error", |mut caller: Caller<'_, PluginStore>, ptr: i32, len: i32| {
+            record_host_call(&caller, "log_error");
             let message = read_string_from_memory(&mut caller, ptr, len)?;
             tracing::error!("[Plugin] {}", message);
             Ok(())
         })?;
         
         // Time functions
-        linker.func_wrap("hal9", "current_timestamp", |_caller: Caller<'_, PluginStore>| -> i64 {
+        linker.func_wrap("hal9", "current_timestamp", |caller: Caller<'_, PluginStore>| -> i64 {
+            record_host_call(&caller, "current_timestamp");
             chrono::Utc::now().timestamp_millis()
         })?;
         
         // Memory functions
         linker.func_wrap("hal9", "memory_get", 
             |mut caller: Caller<'_, PluginStore>, key_ptr: i32, key_len: i32, value_ptr: i32| -> i32 {
+            record_host_call(&caller, "memory_get");
+            
             // Check permissions
             let has_perm = caller.data().plugin_context.permissions
                 .contains(&Permission::Hal9Memory);
@@ -344,6 +522,13 @@ impl WasmRuntime {
 
 // ============ Helper Functions ============
 
+fn record_host_call(caller: &Caller<'_, PluginStore>, function: &str) {
+    let state = caller.data();
+    if let Some(metrics) = &state.metrics {
+        metrics.record_plugin_host_call(&state.name, function);
+    }
+}
+
 fn read_string_from_memory(
     caller: &mut Caller<'_, PluginStore>,
     ptr: i32,
@@ -252,6 +252,148 @@ mod tests {
             assert!(true);
         }
     }
+    
+    mod metrics_tests {
+        use std::sync::Arc;
+        use crate::metrics::{Metrics, PluginBudget};
+        use crate::plugins::api::{PluginContext, PluginMetadata, PluginRequirements, ResourceLimits};
+        use crate::plugins::runtime::{BudgetExceeded, RuntimeConfig, WasmRuntime};
+        use crate::plugins::sandbox::SecurityPolicy;
+        
+        /// Counts its calls, logging and reading the clock on each; can also
+        /// spin forever or grow its memory
+        const COUNTER_WAT: &str = r#"
+            (module
+              (import "hal9" "log_info" (func $log_info (param i32 i32)))
+              (import "hal9" "current_timestamp" (func $now (result i64)))
+              (memory (export "memory") 1)
+              (data (i32.const 0) "tick")
+              (global $count (mut i32) (i32.const 0))
+              (func (export "count") (result i32)
+                (call $log_info (i32.const 0) (i32.const 4))
+                (drop (call $now))
+                (global.set $count (i32.add (global.get $count) (i32.const 1)))
+                (global.get $count))
+              (func (export "spin")
+                (loop $forever (br $forever)))
+              (func (export "grow") (param $pages i32) (result i32)
+                (memory.grow (local.get $pages))))
+        "#;
+        
+        fn runtime(metrics: &Arc<Metrics>) -> WasmRuntime {
+            let config = RuntimeConfig {
+                fuel_per_call: 100_000,
+                enable_epoch_interruption: false,
+                enable_cache: false,
+                ..Default::default()
+            };
+            let mut runtime = WasmRuntime::new(config).unwrap();
+            runtime.set_metrics(metrics.clone());
+            runtime
+        }
+        
+        async fn load_counter(runtime: &WasmRuntime, id: &str, max_memory_bytes: u64) {
+            let metadata = PluginMetadata {
+                id: uuid::Uuid::new_v4(),
+                name: id.to_string(),
+                version: "1.0.0".to_string(),
+                author: "Test".to_string(),
+                description: "Counting test plugin".to_string(),
+                license: "MIT".to_string(),
+                repository: None,
+                homepage: None,
+                capabilities: vec![],
+                requirements: PluginRequirements {
+                    min_hal9_version: "0.1.0".to_string(),
+                    max_memory_mb: 1,
+                    required_permissions: vec![],
+                    dependencies: vec![],
+                },
+            };
+            let context = PluginContext {
+                plugin_id: metadata.id,
+                config: serde_json::Value::Null,
+                permissions: vec![],
+                resource_limits: ResourceLimits {
+                    max_memory_bytes,
+                    max_cpu_percent: 25.0,
+                    max_execution_time_ms: 5000,
+                    max_file_size_bytes: 0,
+                    max_network_connections: 0,
+                },
+                host_version: "0.1.0".to_string(),
+            };
+            runtime
+                .load_plugin(id, COUNTER_WAT.as_bytes(), metadata, context, &SecurityPolicy::default())
+                .await
+                .unwrap();
+        }
+        
+        fn budget_of(err: &anyhow::Error) -> PluginBudget {
+            err.downcast_ref::<BudgetExceeded>().expect("not a budget error").budget
+        }
+        
+        #[tokio::test]
+        async fn test_counting_plugin_metrics() {
+            let metrics = Arc::new(Metrics::new());
+            let runtime = runtime(&metrics);
+            load_counter(&runtime, "counter", 1 << 20).await;
+            
+            for expected in 1..=5 {
+                let results = runtime.call_function("counter", "count", &[]).await.unwrap();
+                assert_eq!(results[0].unwrap_i32(), expected);
+            }
+            assert!(runtime.call_function("counter", "missing", &[]).await.is_err());
+            
+            let stats = metrics.plugin_stats("counter").unwrap();
+            assert_eq!((stats.invocations, stats.errors), (5, 0));
+            assert_eq!(stats.host_calls["log_info"], 5);
+            assert_eq!(stats.host_calls["current_timestamp"], 5);
+            assert_eq!(stats.memory_bytes, 65536);
+            let count = &stats.execution["count"];
+            assert_eq!(count.count, 5);
+            assert!(count.buckets.iter().all(|bucket| *bucket <= 5));
+            assert!(count.sum_seconds > 0.0);
+        }
+        
+        #[tokio::test]
+        async fn test_fuel_exhaustion_terminates_plugin() {
+            let metrics = Arc::new(Metrics::new());
+            let runtime = runtime(&metrics);
+            load_counter(&runtime, "spinner", 1 << 20).await;
+            load_counter(&runtime, "counter", 1 << 20).await;
+            
+            let err = runtime.call_function("spinner", "spin", &[]).await.unwrap_err();
+            assert_eq!(budget_of(&err), PluginBudget::Cpu);
+            
+            let stats = metrics.plugin_stats("spinner").unwrap();
+            assert_eq!((stats.invocations, stats.errors, stats.cpu_exhaustions), (1, 1, 1));
+            assert_eq!(stats.memory_bytes, 0);
+            
+            // The spinner is gone; the host and other plugins carry on
+            let err = runtime.call_function("spinner", "count", &[]).await.unwrap_err();
+            assert!(err.to_string().contains("Plugin not found"), "{}", err);
+            let results = runtime.call_function("counter", "count", &[]).await.unwrap();
+            assert_eq!(results[0].unwrap_i32(), 1);
+        }
+        
+        #[tokio::test]
+        async fn test_memory_budget_terminates_plugin() {
+            let metrics = Arc::new(Metrics::new());
+            let runtime = runtime(&metrics);
+            load_counter(&runtime, "grower", 2 * 65536).await;
+            
+            // Within budget
+            let results = runtime.call_function("grower", "grow", &[wasmtime::Val::I32(1)]).await.unwrap();
+            assert_eq!(results[0].unwrap_i32(), 1);
+            assert_eq!(metrics.plugin_stats("grower").unwrap().memory_bytes, 2 * 65536);
+            
+            let err = runtime.call_function("grower", "grow", &[wasmtime::Val::I32(8)]).await.unwrap_err();
+            assert_eq!(budget_of(&err), PluginBudget::Memory);
+            let stats = metrics.plugin_stats("grower").unwrap();
+            assert_eq!((stats.invocations, stats.memory_exhaustions), (2, 1));
+        }
+    }
 }
 
 // Helper functions for tests
@@ -221,6 +221,624 @@ pub async fn export_metrics(server: Arc<HAL9Server>) -> String {
         );
     }
     
+    // Peer connection failures by reason
+    for (reason, count) in &snapshot.peer_connection_failures {
+        write_metric(
+            &mut output,
+            "hal9_peer_connection_failures_total",
+            "Failed connections to or from peer servers by reason",
+            MetricType::Counter,
+            *count as f64,
+            &[("server_id", server_id), ("reason", reason)],
+        );
+    }
+    
+    // Inter-server transport bytes before and after compression
+    for (direction, stats) in [("sent", &snapshot.transport.sent), ("received", &snapshot.transport.received)] {
+        write_metric(
+            &mut output,
+            "hal9_transport_messages_total",
+            "Messages exchanged with peer servers",
+            MetricType::Counter,
+            stats.messages as f64,
+            &[("server_id", server_id), ("direction", direction)],
+        );
+        write_metric(
+            &mut output,
+            "hal9_transport_compressed_messages_total",
+            "Messages exchanged with peer servers with a zstd-compressed body",
+            MetricType::Counter,
+            stats.compressed_messages as f64,
+            &[("server_id", server_id), ("direction", direction)],
+        );
+        for (encoding, bytes) in [("uncompressed", stats.uncompressed_bytes), ("wire", stats.wire_bytes)] {
+            write_metric(
+                &mut output,
+                "hal9_transport_bytes_total",
+                "Message body bytes exchanged with peer servers, as serialized and as sent",
+                MetricType::Counter,
+                bytes as f64,
+                &[("server_id", server_id), ("direction", direction), ("encoding", encoding)],
+            );
+        }
+        write_metric(
+            &mut output,
+            "hal9_transport_compression_ratio",
+            "Wire bytes per serialized byte exchanged with peer servers",
+            MetricType::Gauge,
+            stats.compression_ratio(),
+            &[("server_id", server_id), ("direction", direction)],
+        );
+    }
+    
+    write_metric(
+        &mut output,
+        "hal9_transport_batches_total",
+        "Batch messages sent to peer servers",
+        MetricType::Counter,
+        snapshot.transport.batches_sent as f64,
+        &[("server_id", server_id)],
+    );
+    
+    write_metric(
+        &mut output,
+        "hal9_transport_batched_signals_total",
+        "Signals sent to peer servers inside batch messages",
+        MetricType::Counter,
+        snapshot.transport.batched_signals as f64,
+        &[("server_id", server_id)],
+    );
+    
+    // WASM plugins
+    for (plugin, stats) in &snapshot.plugins {
+        let labels = [("server_id", server_id), ("plugin", plugin.as_str())];
+        write_metric(
+            &mut output,
+            "hal9_plugin_invocations_total",
+            "Calls into plugin exports",
+            MetricType::Counter,
+            stats.invocations as f64,
+            &labels,
+        );
+        write_metric(
+            &mut output,
+            "hal9_plugin_errors_total",
+            "Failed calls into plugin exports, including budget terminations",
+            MetricType::Counter,
+            stats.errors as f64,
+            &labels,
+        );
+        for (budget, count) in [("cpu", stats.cpu_exhaustions), ("memory", stats.memory_exhaustions)] {
+            write_metric(
+                &mut output,
+                "hal9_plugin_budget_exhaustions_total",
+                "Plugin calls terminated for exceeding their fuel, epoch or memory budget",
+                MetricType::Counter,
+                count as f64,
+                &[("server_id", server_id), ("plugin", plugin), ("budget", budget)],
+            );
+        }
+        for (function, count) in &stats.host_calls {
+            write_metric(
+                &mut output,
+                "hal9_plugin_host_calls_total",
+                "Host function calls made by plugins",
+                MetricType::Counter,
+                *count as f64,
+                &[("server_id", server_id), ("plugin", plugin), ("function", function)],
+            );
+        }
+        write_metric(
+            &mut output,
+            "hal9_plugin_memory_bytes",
+            "Current plugin linear memory size",
+            MetricType::Gauge,
+            stats.memory_bytes as f64,
+            &labels,
+        );
+        write_metric(
+            &mut output,
+            "hal9_plugin_table_elements",
+            "Current plugin table size",
+            MetricType::Gauge,
+            stats.table_elements as f64,
+            &labels,
+        );
+        for (function, histogram) in &stats.execution {
+            write_bucketed_histogram(
+                &mut output,
+                "hal9_plugin_execution_duration_seconds",
+                "Plugin export execution time",
+                &crate::metrics::PLUGIN_DURATION_BUCKETS,
+                histogram,
+                &[("server_id", server_id), ("plugin", plugin), ("function", function)],
+            );
+        }
+    }
+    
+    // Warm pools
+    for (neuron_type, stats) in &snapshot.warm_pools {
+        let labels = [("server_id", server_id), ("neuron_type", neuron_type.as_str())];
+        for (result, count) in [("hit", stats.hits), ("miss", stats.misses)] {
+            write_metric(
+                &mut output,
+                "hal9_warm_pool_checkouts_total",
+                "Warm pool checkouts, served by an idle instance (hit) or on demand (miss)",
+                MetricType::Counter,
+                count as f64,
+                &[("server_id", server_id), ("neuron_type", neuron_type), ("result", result)],
+            );
+        }
+        write_metric(
+            &mut output,
+            "hal9_warm_pool_recycled_total",
+            "Idle pooled instances replaced after the idle TTL",
+            MetricType::Counter,
+            stats.recycled as f64,
+            &labels,
+        );
+        write_metric(
+            &mut output,
+            "hal9_warm_pool_idle_instances",
+            "Idle instances ready in the warm pool",
+            MetricType::Gauge,
+            stats.idle as f64,
+            &labels,
+        );
+        write_bucketed_histogram(
+            &mut output,
+            "hal9_warm_pool_instantiation_duration_seconds",
+            "Time to create a pooled instance",
+            &crate::metrics::PLUGIN_DURATION_BUCKETS,
+            &stats.instantiation,
+            &labels,
+        );
+    }
+    
+    // Local model batching
+    for (model, stats) in &snapshot.local_batches {
+        let labels = [("server_id", server_id), ("model", model.as_str())];
+        let sizes = crate::metrics::HistogramStats {
+            count: stats.batches,
+            sum_seconds: stats.batched_requests as f64,
+            buckets: stats.batch_sizes.clone(),
+        };
+        write_bucketed_histogram(
+            &mut output,
+            "hal9_local_batch_size",
+            "Requests per batch submitted to a local model",
+            &crate::metrics::LOCAL_BATCH_SIZE_BUCKETS,
+            &sizes,
+            &labels,
+        );
+        write_bucketed_histogram(
+            &mut output,
+            "hal9_local_batch_queue_seconds",
+            "Time requests waited for their local model batch",
+            &crate::metrics::PLUGIN_DURATION_BUCKETS,
+            &stats.queue_latency,
+            &labels,
+        );
+        write_metric(
+            &mut output,
+            "hal9_local_batch_bypassed_total",
+            "Local model requests sent without batching because their deadline was near",
+            MetricType::Counter,
+            stats.bypassed as f64,
+            &labels,
+        );
+    }
+    
+    // Response validation outcomes by validator
+    for (key, count) in &snapshot.validation_results {
+        let (validator, outcome) = key.rsplit_once(':').unwrap_or((key.as_str(), "unknown"));
+        write_metric(
+            &mut output,
+            "hal9_validation_results_total",
+            "Response validation outcomes by validator",
+            MetricType::Counter,
+            *count as f64,
+            &[("server_id", server_id), ("validator", validator), ("outcome", outcome)],
+        );
+    }
+    
+    write_metric(
+        &mut output,
+        "hal9_validation_retries_total",
+        "Neuron re-prompts after failed response validation",
+        MetricType::Counter,
+        snapshot.validation_retries as f64,
+        &[("server_id", server_id)],
+    );
+
+    // Recovery playbook steps by outcome
+    for (key, count) in &snapshot.recovery_steps {
+        let (step, outcome) = key.rsplit_once(':').unwrap_or((key.as_str(), "unknown"));
+        write_metric(
+            &mut output,
+            "hal9_recovery_steps_total",
+            "Recovery steps attempted on failed signals by outcome",
+            MetricType::Counter,
+            *count as f64,
+            &[("server_id", server_id), ("step", step), ("outcome", outcome)],
+        );
+    }
+
+    // Speculative child signals by outcome and the cost of cancelled ones
+    for (outcome, count) in &snapshot.speculation {
+        write_metric(
+            &mut output,
+            "hal9_speculation_total",
+            "Speculative child signals started, confirmed and cancelled",
+            MetricType::Counter,
+            *count as f64,
+            &[("server_id", server_id), ("outcome", outcome)],
+        );
+    }
+    write_metric(
+        &mut output,
+        "hal9_speculation_wasted_dollars_total",
+        "Cost of speculative child signals cancelled by their parent's final output",
+        MetricType::Counter,
+        snapshot.speculation_wasted,
+        &[("server_id", server_id)],
+    );
+
+    // Ingestion sources: outcomes, throughput, lag and pauses
+    for source in &snapshot.ingestion {
+        let name = source.name.as_str();
+        let outcomes = [
+            ("accepted", source.accepted),
+            ("duplicate", source.duplicates),
+            ("quarantined", source.quarantined),
+            ("held", source.held_messages),
+            ("rejected", source.rejected),
+        ];
+        for (outcome, count) in outcomes {
+            write_metric(
+                &mut output,
+                "hal9_ingest_messages_total",
+                "Inbound messages of each ingestion source by outcome",
+                MetricType::Counter,
+                count as f64,
+                &[("server_id", server_id), ("source", name), ("outcome", outcome)],
+            );
+        }
+        write_metric(
+            &mut output,
+            "hal9_ingest_throughput_per_second",
+            "Chains an ingestion source started per second over the last minute",
+            MetricType::Gauge,
+            source.throughput_per_sec,
+            &[("server_id", server_id), ("source", name)],
+        );
+        if let Some(lag) = source.lag {
+            write_metric(
+                &mut output,
+                "hal9_ingest_lag",
+                "Messages on a Kafka source's topic not handled yet",
+                MetricType::Gauge,
+                lag as f64,
+                &[("server_id", server_id), ("source", name)],
+            );
+        }
+        write_metric(
+            &mut output,
+            "hal9_ingest_paused",
+            "Whether an ingestion source is paused through the admin API",
+            MetricType::Gauge,
+            if source.paused { 1.0 } else { 0.0 },
+            &[("server_id", server_id), ("source", name)],
+        );
+    }
+
+    // Per-neuron queues and overflow
+    for (neuron_id, queue) in &snapshot.neuron_queues {
+        write_metric(
+            &mut output,
+            "hal9_neuron_in_flight",
+            "Signals a neuron is processing",
+            MetricType::Gauge,
+            queue.in_flight as f64,
+            &[("server_id", server_id), ("neuron_id", neuron_id)],
+        );
+        write_metric(
+            &mut output,
+            "hal9_neuron_queue_depth",
+            "Signals waiting for a neuron processing slot",
+            MetricType::Gauge,
+            queue.queued as f64,
+            &[("server_id", server_id), ("neuron_id", neuron_id)],
+        );
+        for (policy, count) in [("reject", queue.rejected), ("shed", queue.shed), ("block", queue.blocked)] {
+            write_metric(
+                &mut output,
+                "hal9_neuron_overflow_total",
+                "Signals arriving at a full neuron queue by overflow policy",
+                MetricType::Counter,
+                count as f64,
+                &[("server_id", server_id), ("neuron_id", neuron_id), ("policy", policy)],
+            );
+        }
+    }
+
+    write_metric(
+        &mut output,
+        "hal9_rate_limit_degraded",
+        "1 while distributed rate limits fall back to per-instance buckets",
+        MetricType::Gauge,
+        if snapshot.rate_limit_degraded { 1.0 } else { 0.0 },
+        &[("server_id", server_id)],
+    );
+
+    // SQLite connection pools and write contention
+    for (database, pools) in &snapshot.database_pools {
+        for (pool, state, count) in [
+            ("reader", "in_use", pools.reader_in_use),
+            ("reader", "idle", pools.reader_idle),
+            ("writer", "in_use", pools.writer_in_use),
+            ("writer", "idle", pools.writer_idle),
+        ] {
+            write_metric(
+                &mut output,
+                "hal9_db_connections",
+                "Pooled database connections by pool and state",
+                MetricType::Gauge,
+                count as f64,
+                &[("server_id", server_id), ("database", database), ("pool", pool), ("state", state)],
+            );
+        }
+        write_metric(
+            &mut output,
+            "hal9_db_writes_total",
+            "Writes run through the serialized writer",
+            MetricType::Counter,
+            pools.writes as f64,
+            &[("server_id", server_id), ("database", database)],
+        );
+        write_metric(
+            &mut output,
+            "hal9_db_write_wait_ms",
+            "Average time writes waited behind other writes",
+            MetricType::Gauge,
+            pools.write_wait_ms_avg,
+            &[("server_id", server_id), ("database", database)],
+        );
+        write_metric(
+            &mut output,
+            "hal9_db_write_wait_max_ms",
+            "Longest time a write waited behind other writes",
+            MetricType::Gauge,
+            pools.write_wait_ms_max,
+            &[("server_id", server_id), ("database", database)],
+        );
+        write_metric(
+            &mut output,
+            "hal9_db_busy_retries_total",
+            "Writes retried after SQLite reported the database busy",
+            MetricType::Counter,
+            pools.busy_retries as f64,
+            &[("server_id", server_id), ("database", database)],
+        );
+    }
+
+    // Layer pauses; every layer is reported so unpausing reads as 0
+    for layer in ["L1", "L2", "L3", "L4", "L5", "L6", "L7", "L8", "L9"] {
+        let held = snapshot.paused_layers.get(layer);
+        write_metric(
+            &mut output,
+            "hal9_layer_paused",
+            "1 while an operator has paused the layer",
+            MetricType::Gauge,
+            if held.is_some() { 1.0 } else { 0.0 },
+            &[("server_id", server_id), ("layer", layer)],
+        );
+        write_metric(
+            &mut output,
+            "hal9_layer_held_signals",
+            "Signals waiting for a paused layer to resume",
+            MetricType::Gauge,
+            held.copied().unwrap_or(0) as f64,
+            &[("server_id", server_id), ("layer", layer)],
+        );
+    }
+    write_metric(
+        &mut output,
+        "hal9_layer_pause_spilled_total",
+        "Signals dead-lettered because a paused layer held too many",
+        MetricType::Counter,
+        snapshot.layer_pause_spilled as f64,
+        &[("server_id", server_id)],
+    );
+
+    // Read-only mode
+    if let Some(read_only) = &snapshot.read_only {
+        write_metric(
+            &mut output,
+            "hal9_read_only",
+            "1 while the API refuses writes for maintenance",
+            MetricType::Gauge,
+            if read_only.active { 1.0 } else { 0.0 },
+            &[("server_id", server_id)],
+        );
+        write_metric(
+            &mut output,
+            "hal9_read_only_held_memory_writes",
+            "Memory writes waiting for read-only mode to end",
+            MetricType::Gauge,
+            read_only.held_writes as f64,
+            &[("server_id", server_id)],
+        );
+        write_metric(
+            &mut output,
+            "hal9_read_only_skipped_memory_writes_total",
+            "Memory writes dropped while read-only",
+            MetricType::Counter,
+            read_only.skipped_writes as f64,
+            &[("server_id", server_id)],
+        );
+        write_metric(
+            &mut output,
+            "hal9_read_only_refused_requests_total",
+            "API requests refused while read-only",
+            MetricType::Counter,
+            read_only.refused_requests as f64,
+            &[("server_id", server_id)],
+        );
+    }
+
+    if let Some(cold_tier) = &snapshot.cold_tier {
+        let labels = [("server_id", server_id), ("backend", cold_tier.backend.as_str())];
+        write_metric(
+            &mut output,
+            "hal9_memory_cold_reads_total",
+            "Memory entries whose content was fetched from the cold tier",
+            MetricType::Counter,
+            cold_tier.reads as f64,
+            &labels,
+        );
+        write_metric(
+            &mut output,
+            "hal9_memory_cold_promotions_total",
+            "Cold memory entries moved back to the hot tier for being read often",
+            MetricType::Counter,
+            cold_tier.promotions as f64,
+            &labels,
+        );
+    }
+
+    // Genius game WebSocket connections and how far behind they are
+    if let Some(game) = &snapshot.game_connections {
+        write_metric(
+            &mut output,
+            "hal9_game_connections",
+            "Open genius game WebSocket connections",
+            MetricType::Gauge,
+            game.connections.len() as f64,
+            &[("server_id", server_id)],
+        );
+        for connection in &game.connections {
+            write_metric(
+                &mut output,
+                "hal9_game_outbound_queue_depth",
+                "Messages waiting to be written to a genius game connection",
+                MetricType::Gauge,
+                connection.queue_depth as f64,
+                &[("server_id", server_id), ("connection", connection.connection_id.as_str())],
+            );
+        }
+        write_metric(
+            &mut output,
+            "hal9_game_outbound_coalesced_total",
+            "Snapshots sent in place of the state updates a lagging connection missed",
+            MetricType::Counter,
+            game.coalesced as f64,
+            &[("server_id", server_id)],
+        );
+        write_metric(
+            &mut output,
+            "hal9_game_lag_disconnects_total",
+            "Genius game connections closed for falling too far behind",
+            MetricType::Counter,
+            game.lag_disconnects as f64,
+            &[("server_id", server_id)],
+        );
+    }
+
+    // Layer compression from signal traffic within the window
+    for flow in &snapshot.layer_compression {
+        let boundary = flow.boundary();
+        let labels = [("server_id", server_id), ("boundary", boundary.as_str()), ("direction", flow.direction.as_str())];
+        write_metric(
+            &mut output,
+            "hal9_layer_compression_ratio",
+            "Content entering the sending layer over content it forwarded across the boundary",
+            MetricType::Gauge,
+            flow.ratio,
+            &labels,
+        );
+        if flow.health.is_some() {
+            write_metric(
+                &mut output,
+                "hal9_layer_compression_unhealthy",
+                "1 while a boundary's compression ratio is outside the healthy band",
+                MetricType::Gauge,
+                if flow.is_healthy() { 0.0 } else { 1.0 },
+                &labels,
+            );
+        }
+    }
+
+    // Gradient activity: backward signals per boundary
+    for (boundary, count) in &snapshot.backward_signals {
+        write_metric(
+            &mut output,
+            "hal9_backward_signals_total",
+            "Backward signals that crossed the boundary",
+            MetricType::Counter,
+            *count as f64,
+            &[("server_id", server_id), ("boundary", boundary.as_str())],
+        );
+    }
+
+    // Replica load for autoscaling, e.g. through prometheus-adapter
+    if let Some(load) = &snapshot.autoscaling {
+        write_metric(
+            &mut output,
+            "hal9_pending_signals",
+            "Signals received that no neuron has started processing",
+            MetricType::Gauge,
+            load.pending_signals as f64,
+            &[("server_id", server_id)],
+        );
+        write_metric(
+            &mut output,
+            "hal9_oldest_pending_signal_age_seconds",
+            "How long the longest-waiting pending signal has waited",
+            MetricType::Gauge,
+            load.oldest_pending_age_secs,
+            &[("server_id", server_id)],
+        );
+        write_metric(
+            &mut output,
+            "hal9_claude_pool_saturation",
+            "Share of processing slots, and the Claude connections behind them, in use",
+            MetricType::Gauge,
+            load.pool_saturation,
+            &[("server_id", server_id)],
+        );
+        write_metric(
+            &mut output,
+            "hal9_draining",
+            "1 while the server drains before termination",
+            MetricType::Gauge,
+            if load.draining { 1.0 } else { 0.0 },
+            &[("server_id", server_id)],
+        );
+    }
+
+    // SLO burn rates and error budgets
+    for slo in server.slo_status() {
+        for (window, burn_rate) in [("fast", slo.fast_burn_rate), ("slow", slo.slow_burn_rate)] {
+            write_metric(
+                &mut output,
+                "hal9_slo_burn_rate",
+                "Bad event ratio over the ratio the SLO allows, per alert window",
+                MetricType::Gauge,
+                burn_rate,
+                &[("server_id", server_id), ("slo", &slo.name), ("window", window)],
+            );
+        }
+        write_metric(
+            &mut output,
+            "hal9_slo_error_budget_remaining",
+            "Share of the SLO window's error budget left",
+            MetricType::Gauge,
+            slo.budget_remaining,
+            &[("server_id", server_id), ("slo", &slo.name)],
+        );
+    }
+
     // Authentication metrics (if enabled)
     if server.user_manager.is_some() {
         if let Ok(user_count) = server.get_active_user_count().await {
@@ -309,6 +927,44 @@ pub async fn export_metrics(server: Arc<HAL9Server>) -> String {
         );
     }
     
+    // Poll times of signal tasks, while profiling
+    let profiler = server.profiler();
+    if profiler.is_active() {
+        let polls = profiler.polls().stats();
+        write_bucketed_histogram(
+            &mut output,
+            "hal9_task_poll_duration_seconds",
+            "Time a signal task held its worker thread per poll",
+            &crate::metrics::PLUGIN_DURATION_BUCKETS,
+            &polls.polls,
+            &[("server_id", server_id)],
+        );
+        write_metric(
+            &mut output,
+            "hal9_task_polls_over_budget_total",
+            "Polls of signal tasks longer than profiling.poll_budget_ms",
+            MetricType::Counter,
+            polls.over_budget as f64,
+            &[("server_id", server_id)],
+        );
+        write_metric(
+            &mut output,
+            "hal9_task_stalls_total",
+            "Polls of signal tasks blocking their worker thread for profiling.blocking_poll_ms or longer",
+            MetricType::Counter,
+            polls.stalls as f64,
+            &[("server_id", server_id)],
+        );
+        write_metric(
+            &mut output,
+            "hal9_task_monitored",
+            "Monitored signal tasks not finished yet",
+            MetricType::Gauge,
+            polls.tasks as f64,
+            &[("server_id", server_id)],
+        );
+    }
+    
     output
 }
 
@@ -331,7 +987,11 @@ fn write_metric(
         }).unwrap();
     }
     
-    // Write metric with labels
+    write_sample(output, name, value, labels);
+}
+
+/// Write one sample line, without HELP and TYPE
+fn write_sample(output: &mut String, name: &str, value: f64, labels: &[(&str, &str)]) {
     write!(output, "{}", name).unwrap();
     if !labels.is_empty() {
         write!(output, "{{").unwrap();
@@ -346,6 +1006,33 @@ fn write_metric(
     writeln!(output, " {}", value).unwrap();
 }
 
+/// Write a histogram with real bucket counts: `_bucket` lines, `_sum` and
+/// `_count`
+fn write_bucketed_histogram(
+    output: &mut String,
+    name: &str,
+    help: &str,
+    bounds: &[f64],
+    stats: &crate::metrics::HistogramStats,
+    base_labels: &[(&str, &str)],
+) {
+    if !output.contains(&format!("# HELP {} ", name)) {
+        writeln!(output, "# HELP {} {}", name, help).unwrap();
+        writeln!(output, "# TYPE {} histogram", name).unwrap();
+    }
+    
+    let bucket_name = format!("{}_bucket", name);
+    let bounds: Vec<String> = bounds.iter().map(|bound| bound.to_string()).collect();
+    let les = bounds.iter().map(String::as_str).chain(["+Inf"]);
+    for (le, count) in les.zip(stats.buckets.iter().chain([&stats.count])) {
+        let mut labels = base_labels.to_vec();
+        labels.push(("le", le));
+        write_sample(output, &bucket_name, *count as f64, &labels);
+    }
+    write_sample(output, &format!("{}_sum", name), stats.sum_seconds, base_labels);
+    write_sample(output, &format!("{}_count", name), stats.count as f64, base_labels);
+}
+
 /// Write histogram buckets
 fn write_histogram_buckets(
     output: &mut String,
@@ -0,0 +1,571 @@
+//! Prompt composition accounting
+//!
+//! A neuron's prompt is put together from sections: the system prompt the
+//! provider is sent for the neuron's layer, the layer instructions (the
+//! neuron's configured prompt, the signal framing, tool and output format
+//! instructions), memory recalled for the signal, corrections (learned
+//! prompt adjustments and the gradients of backward signals) and the user
+//! content the signal carries. The neuron builds its prompt as a
+//! [`ComposedPrompt`] and, for the first provider call answering a signal,
+//! [`PromptAccounting`] counts each section with the tokenizer of the model
+//! serving it. When the provider reports the call's prompt tokens, the
+//! counts are scaled to add up to them. The counts go into the
+//! `prompt.sections` metadata of the signals the response produced, and
+//! into daily UTC buckets per neuron kept for [`RETENTION_DAYS`]. Like
+//! tagged spend, they are kept in memory and start over when the server
+//! restarts.
+//!
+//! The report gives each layer's average tokens per prompt by section, with
+//! what reading the static sections from the provider's prompt cache or
+//! trimming each section would have saved, and flags neurons whose static
+//! sections take more than `static_share_threshold` of their prompts.
+//! Nothing about the prompts sent changes.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+use chrono::{DateTime, Duration, FixedOffset, NaiveDate, Utc};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+
+use hal9_core::{
+    config::{BudgetPeriod, PromptCompositionConfig},
+    metadata_schema::keys,
+    Error, Result,
+};
+
+use crate::{
+    budget_period::{next_reset, period_start},
+    claude::TokenUsage,
+    model_registry::ModelRegistry,
+};
+
+/// Metadata key of the prompt tokens by section of the call that produced
+/// a signal
+pub const PROMPT_SECTIONS_KEY: &str = keys::prompt::SECTIONS;
+
+/// Metadata key of the tokenizer they were counted with
+pub const PROMPT_TOKENIZER_KEY: &str = keys::prompt::TOKENIZER;
+
+/// Days of prompt composition kept, enough for the current and previous
+/// month
+pub const RETENTION_DAYS: i64 = 62;
+
+/// Model name prefixes counted with `o200k_base`
+const O200K_MODEL_PREFIXES: [&str; 4] = ["gpt-4o", "o1", "o3", "o4"];
+
+/// Part of a prompt
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PromptSection {
+    /// System prompt of the neuron's layer
+    System,
+    /// The neuron's prompt, the signal framing and tool and output format
+    /// instructions
+    LayerInstructions,
+    /// Memory recalled for the signal
+    Memory,
+    /// Learned prompt adjustments and backward signal gradients
+    Corrections,
+    /// What the signal carries
+    UserContent,
+}
+
+impl PromptSection {
+    pub const ALL: [PromptSection; 5] = [
+        PromptSection::System,
+        PromptSection::LayerInstructions,
+        PromptSection::Memory,
+        PromptSection::Corrections,
+        PromptSection::UserContent,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PromptSection::System => "system",
+            PromptSection::LayerInstructions => "layer_instructions",
+            PromptSection::Memory => "memory",
+            PromptSection::Corrections => "corrections",
+            PromptSection::UserContent => "user_content",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|section| section.as_str() == value)
+    }
+
+    /// Whether the section reads the same in every prompt a neuron sends,
+    /// so a provider's prompt cache could serve it
+    pub fn is_static(&self) -> bool {
+        matches!(self, PromptSection::System | PromptSection::LayerInstructions)
+    }
+
+    fn index(&self) -> usize {
+        *self as usize
+    }
+}
+
+/// BPE vocabulary prompts are counted with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Tokenizer {
+    Cl100kBase,
+    O200kBase,
+}
+
+impl Tokenizer {
+    /// Tokenizer counting the prompts of `model`. Claude's tokenizer is not
+    /// published, so Claude and local models are counted with
+    /// `cl100k_base`; their counts are scaled to the provider's once it
+    /// reports them.
+    pub fn for_model(model: &str) -> Self {
+        if O200K_MODEL_PREFIXES.iter().any(|prefix| model.starts_with(prefix)) {
+            Tokenizer::O200kBase
+        } else {
+            Tokenizer::Cl100kBase
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Tokenizer::Cl100kBase => "cl100k_base",
+            Tokenizer::O200kBase => "o200k_base",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "cl100k_base" => Some(Tokenizer::Cl100kBase),
+            "o200k_base" => Some(Tokenizer::O200kBase),
+            _ => None,
+        }
+    }
+
+    /// Tokens of `text`, special tokens counted as plain text
+    pub fn count(&self, text: &str) -> u64 {
+        let bpe = match self {
+            Tokenizer::Cl100kBase => tiktoken_rs::cl100k_base_singleton(),
+            Tokenizer::O200kBase => tiktoken_rs::o200k_base_singleton(),
+        };
+        let tokens = bpe.lock().encode_ordinary(text).len();
+        tokens as u64
+    }
+}
+
+/// A prompt kept as the sections it was built from, in order
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ComposedPrompt {
+    parts: Vec<(PromptSection, String)>,
+}
+
+impl ComposedPrompt {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append `text` as part of `section`
+    pub fn push(&mut self, section: PromptSection, text: impl Into<String>) {
+        let text = text.into();
+        if !text.is_empty() {
+            self.parts.push((section, text));
+        }
+    }
+
+    /// Append every part of `other`
+    pub fn append(&mut self, other: ComposedPrompt) {
+        self.parts.extend(other.parts);
+    }
+
+    pub fn parts(&self) -> &[(PromptSection, String)] {
+        &self.parts
+    }
+
+    /// The prompt as sent
+    pub fn text(&self) -> String {
+        self.parts.iter().map(|(_, text)| text.as_str()).collect()
+    }
+
+    /// Tokens of each section with `tokenizer`, the `system_prompt` sent
+    /// alongside the prompt included
+    pub fn count(&self, system_prompt: &str, tokenizer: Tokenizer) -> PromptComposition {
+        let mut tokens = BTreeMap::new();
+        let parts = std::iter::once((PromptSection::System, system_prompt))
+            .chain(self.parts.iter().map(|(section, text)| (*section, text.as_str())));
+        for (section, text) in parts.filter(|(_, text)| !text.is_empty()) {
+            *tokens.entry(section).or_insert(0) += tokenizer.count(text);
+        }
+        PromptComposition { tokenizer, tokens, calibrated: false }
+    }
+}
+
+/// Split a prompt adjusted from `base` into the start it kept of `base`, the
+/// part adjustments changed and the end it kept of `base`
+pub fn split_adjusted<'a>(base: &str, adjusted: &'a str) -> (&'a str, &'a str, &'a str) {
+    let prefix = base.char_indices()
+        .zip(adjusted.chars())
+        .find(|((_, a), b)| a != b)
+        .map_or(base.len().min(adjusted.len()), |((at, _), _)| at);
+    let rest = &adjusted[prefix..];
+    let suffix = base[prefix..].chars()
+        .rev()
+        .zip(rest.chars().rev())
+        .take_while(|(a, b)| a == b)
+        .map(|(a, _)| a.len_utf8())
+        .sum::<usize>();
+    let (changed, kept) = rest.split_at(rest.len() - suffix);
+    (&adjusted[..prefix], changed, kept)
+}
+
+/// Tokens a prompt spent on each section
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PromptComposition {
+    pub tokenizer: Tokenizer,
+    /// Sections the prompt lacks are left out
+    pub tokens: BTreeMap<PromptSection, u64>,
+    /// Scaled to the prompt tokens the provider reported
+    pub calibrated: bool,
+}
+
+impl PromptComposition {
+    pub fn total(&self) -> u64 {
+        self.tokens.values().sum()
+    }
+
+    /// Tokens of the system prompt and layer instructions
+    pub fn static_tokens(&self) -> u64 {
+        self.tokens.iter().filter(|(section, _)| section.is_static()).map(|(_, tokens)| tokens).sum()
+    }
+
+    /// Scale the counts to add up to `reported` prompt tokens. Rounding
+    /// leftovers go to the sections with the largest fractions, the earlier
+    /// section on a tie.
+    pub fn calibrate(&mut self, reported: u64) {
+        let total = self.total();
+        if total == 0 || reported == 0 {
+            return;
+        }
+        let mut shares: Vec<(PromptSection, u64, u64)> = self.tokens
+            .iter()
+            .map(|(section, tokens)| {
+                let scaled = *tokens as u128 * reported as u128;
+                (*section, (scaled / total as u128) as u64, (scaled % total as u128) as u64)
+            })
+            .collect();
+        let leftover = reported - shares.iter().map(|(_, tokens, _)| tokens).sum::<u64>();
+        let mut by_fraction: Vec<usize> = (0..shares.len()).collect();
+        by_fraction.sort_by(|&a, &b| shares[b].2.cmp(&shares[a].2).then(a.cmp(&b)));
+        for &i in by_fraction.iter().take(leftover as usize) {
+            shares[i].1 += 1;
+        }
+        self.tokens = shares.into_iter().map(|(section, tokens, _)| (section, tokens)).collect();
+        self.calibrated = true;
+    }
+
+    /// Write the counts into signal metadata
+    pub fn apply_to(&self, metadata: &mut HashMap<String, String>) {
+        let sections = self.tokens
+            .iter()
+            .map(|(section, tokens)| format!("{}={}", section.as_str(), tokens))
+            .collect::<Vec<_>>()
+            .join(",");
+        metadata.insert(PROMPT_SECTIONS_KEY.to_string(), sections);
+        metadata.insert(PROMPT_TOKENIZER_KEY.to_string(), self.tokenizer.as_str().to_string());
+    }
+
+    /// Counts written by [`Self::apply_to`], if the metadata has them
+    pub fn from_metadata(metadata: &HashMap<String, String>) -> Option<Self> {
+        let tokenizer = Tokenizer::parse(metadata.get(PROMPT_TOKENIZER_KEY)?)?;
+        let mut tokens = BTreeMap::new();
+        for pair in metadata.get(PROMPT_SECTIONS_KEY)?.split(',').filter(|pair| !pair.is_empty()) {
+            let (section, count) = pair.split_once('=')?;
+            tokens.insert(PromptSection::parse(section)?, count.parse().ok()?);
+        }
+        Some(Self { tokenizer, tokens, calibrated: false })
+    }
+}
+
+/// Prompts counted, with their tokens and prompt cost by section
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SectionTotals {
+    pub prompts: u64,
+    /// By [`PromptSection`] in declaration order
+    pub tokens: [u64; 5],
+    pub cost_usd: [f64; 5],
+}
+
+impl SectionTotals {
+    /// One prompt of `composition`, priced at `price_per_1k` per prompt
+    /// token
+    pub fn of(composition: &PromptComposition, price_per_1k: f64) -> Self {
+        let mut totals = SectionTotals { prompts: 1, ..Default::default() };
+        for (section, tokens) in &composition.tokens {
+            totals.tokens[section.index()] = *tokens;
+            totals.cost_usd[section.index()] = *tokens as f64 / 1000.0 * price_per_1k;
+        }
+        totals
+    }
+
+    fn add(&mut self, other: &SectionTotals) {
+        self.prompts += other.prompts;
+        for i in 0..PromptSection::ALL.len() {
+            self.tokens[i] += other.tokens[i];
+            self.cost_usd[i] += other.cost_usd[i];
+        }
+    }
+
+    fn total_tokens(&self) -> u64 {
+        self.tokens.iter().sum()
+    }
+
+    fn static_tokens(&self) -> u64 {
+        PromptSection::ALL.iter().filter(|section| section.is_static()).map(|section| self.tokens[section.index()]).sum()
+    }
+
+    fn average(&self, tokens: u64) -> f64 {
+        if self.prompts == 0 {
+            0.0
+        } else {
+            tokens as f64 / self.prompts as f64
+        }
+    }
+}
+
+fn share(part: f64, whole: f64) -> f64 {
+    if whole > 0.0 {
+        part / whole
+    } else {
+        0.0
+    }
+}
+
+/// One section of a layer's prompts
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SectionUsage {
+    pub section: PromptSection,
+    pub static_section: bool,
+    /// Tokens per prompt
+    pub average_tokens: f64,
+    /// Of the layer's prompt tokens
+    pub share: f64,
+    /// Prompt cost of the section over the period, which trimming it away
+    /// would have saved
+    pub cost_usd: f64,
+    /// What reading it from the provider's prompt cache would have saved;
+    /// zero for sections that change from prompt to prompt
+    pub caching_savings_usd: f64,
+}
+
+/// A layer's prompts over the period
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LayerComposition {
+    pub layer: String,
+    pub prompts: u64,
+    /// Tokens per prompt
+    pub average_tokens: f64,
+    /// Share of the prompt tokens taken by static sections
+    pub static_share: f64,
+    pub cost_usd: f64,
+    /// What caching every static section would have saved
+    pub caching_savings_usd: f64,
+    /// Every section, in [`PromptSection`] order
+    pub sections: Vec<SectionUsage>,
+}
+
+/// A neuron whose static sections take more than the threshold
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FlaggedNeuron {
+    pub neuron_id: String,
+    pub layer: String,
+    pub prompts: u64,
+    /// Tokens per prompt
+    pub average_tokens: f64,
+    /// Static tokens per prompt
+    pub average_static_tokens: f64,
+    pub static_share: f64,
+}
+
+/// Prompt composition over one period, by layer
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PromptCompositionReport {
+    pub period: BudgetPeriod,
+    pub period_start: DateTime<Utc>,
+    pub period_end: DateTime<Utc>,
+    pub static_share_threshold: f64,
+    pub cache_read_ratio: f64,
+    pub prompts: u64,
+    pub cost_usd: f64,
+    pub caching_savings_usd: f64,
+    /// By layer name
+    pub layers: Vec<LayerComposition>,
+    /// Highest static share first
+    pub flagged_neurons: Vec<FlaggedNeuron>,
+}
+
+/// Section totals by day, layer and neuron
+#[derive(Debug, Default)]
+pub struct PromptLedger {
+    totals: BTreeMap<(NaiveDate, String, String), SectionTotals>,
+}
+
+impl PromptLedger {
+    /// Add a prompt of `neuron_id` on `layer` on the day of `at`, dropping
+    /// days past [`RETENTION_DAYS`]
+    pub fn record(&mut self, layer: &str, neuron_id: &str, totals: SectionTotals, at: DateTime<Utc>) {
+        let day = at.date_naive();
+        self.totals.entry((day, layer.to_string(), neuron_id.to_string())).or_default().add(&totals);
+
+        let oldest = (day - Duration::days(RETENTION_DAYS), String::new(), String::new());
+        if self.totals.first_key_value().is_some_and(|(first, _)| *first < oldest) {
+            self.totals = self.totals.split_off(&oldest);
+        }
+    }
+
+    /// Composition of the `period` containing `now`
+    pub fn report(&self, period: BudgetPeriod, now: DateTime<Utc>, config: &PromptCompositionConfig) -> PromptCompositionReport {
+        let utc = FixedOffset::east_opt(0).unwrap();
+        let start = period_start(period, utc, now);
+        let end = next_reset(period, utc, start);
+
+        let mut layers: BTreeMap<&str, SectionTotals> = BTreeMap::new();
+        let mut neurons: BTreeMap<(&str, &str), SectionTotals> = BTreeMap::new();
+        let days = (start.date_naive(), String::new(), String::new())..(end.date_naive(), String::new(), String::new());
+        for ((_, layer, neuron), totals) in self.totals.range(days) {
+            layers.entry(layer.as_str()).or_default().add(totals);
+            neurons.entry((neuron.as_str(), layer.as_str())).or_default().add(totals);
+        }
+
+        let caching = |section: PromptSection, cost: f64| {
+            if section.is_static() {
+                cost * (1.0 - config.cache_read_ratio)
+            } else {
+                0.0
+            }
+        };
+        let layers: Vec<LayerComposition> = layers
+            .into_iter()
+            .map(|(layer, totals)| {
+                let total_tokens = totals.total_tokens() as f64;
+                let sections: Vec<SectionUsage> = PromptSection::ALL
+                    .into_iter()
+                    .map(|section| {
+                        let tokens = totals.tokens[section.index()];
+                        let cost_usd = totals.cost_usd[section.index()];
+                        SectionUsage {
+                            section,
+                            static_section: section.is_static(),
+                            average_tokens: totals.average(tokens),
+                            share: share(tokens as f64, total_tokens),
+                            cost_usd,
+                            caching_savings_usd: caching(section, cost_usd),
+                        }
+                    })
+                    .collect();
+                LayerComposition {
+                    layer: layer.to_string(),
+                    prompts: totals.prompts,
+                    average_tokens: totals.average(totals.total_tokens()),
+                    static_share: share(totals.static_tokens() as f64, total_tokens),
+                    cost_usd: sections.iter().map(|section| section.cost_usd).sum(),
+                    caching_savings_usd: sections.iter().map(|section| section.caching_savings_usd).sum(),
+                    sections,
+                }
+            })
+            .collect();
+
+        let mut flagged_neurons: Vec<FlaggedNeuron> = neurons
+            .into_iter()
+            .map(|((neuron, layer), totals)| FlaggedNeuron {
+                neuron_id: neuron.to_string(),
+                layer: layer.to_string(),
+                prompts: totals.prompts,
+                average_tokens: totals.average(totals.total_tokens()),
+                average_static_tokens: totals.average(totals.static_tokens()),
+                static_share: share(totals.static_tokens() as f64, totals.total_tokens() as f64),
+            })
+            .filter(|neuron| neuron.static_share > config.static_share_threshold)
+            .collect();
+        flagged_neurons.sort_by(|a, b| b.static_share.total_cmp(&a.static_share).then_with(|| a.neuron_id.cmp(&b.neuron_id)));
+
+        PromptCompositionReport {
+            period,
+            period_start: start,
+            period_end: end,
+            static_share_threshold: config.static_share_threshold,
+            cache_read_ratio: config.cache_read_ratio,
+            prompts: layers.iter().map(|layer| layer.prompts).sum(),
+            cost_usd: layers.iter().map(|layer| layer.cost_usd).sum(),
+            caching_savings_usd: layers.iter().map(|layer| layer.caching_savings_usd).sum(),
+            layers,
+            flagged_neurons,
+        }
+    }
+}
+
+/// Check the flagging threshold and cache price are shares
+pub fn validate(config: &PromptCompositionConfig) -> Result<()> {
+    let invalid = if !(0.0..=1.0).contains(&config.static_share_threshold) {
+        Some("static_share_threshold must be between 0 and 1")
+    } else if !(0.0..=1.0).contains(&config.cache_read_ratio) {
+        Some("cache_read_ratio must be between 0 and 1")
+    } else {
+        None
+    };
+    match invalid {
+        Some(message) => Err(Error::Config(format!("Invalid prompt_composition config: {}", message))),
+        None => Ok(()),
+    }
+}
+
+/// Counts the prompts of every neuron of the server
+pub struct PromptAccounting {
+    config: PromptCompositionConfig,
+    models: Arc<ModelRegistry>,
+    /// Model of calls whose usage names none
+    default_model: String,
+    ledger: Mutex<PromptLedger>,
+}
+
+impl PromptAccounting {
+    pub fn new(config: PromptCompositionConfig, models: Arc<ModelRegistry>, default_model: &str) -> Self {
+        Self {
+            config,
+            models,
+            default_model: default_model.to_string(),
+            ledger: Mutex::new(PromptLedger::default()),
+        }
+    }
+
+    pub fn config(&self) -> &PromptCompositionConfig {
+        &self.config
+    }
+
+    /// Count `prompt`, sent by `neuron_id` on `layer` with `system_prompt`,
+    /// and add it to the ledger. The counts are scaled to the prompt tokens
+    /// of `usage` when a provider reported it for a model; mock usage names
+    /// none. None while disabled.
+    pub fn record(
+        &self,
+        neuron_id: &str,
+        layer: &str,
+        prompt: &ComposedPrompt,
+        system_prompt: &str,
+        usage: Option<&TokenUsage>,
+    ) -> Option<PromptComposition> {
+        if !self.config.enabled {
+            return None;
+        }
+        let model = usage.and_then(|usage| usage.model.as_deref()).unwrap_or(&self.default_model);
+        let mut composition = prompt.count(system_prompt, Tokenizer::for_model(model));
+        if let Some(usage) = usage.filter(|usage| usage.model.is_some()) {
+            composition.calibrate(usage.prompt_tokens as u64);
+        }
+        let (price_per_1k, _) = self.models.pricing(model);
+        self.ledger.lock().record(layer, neuron_id, SectionTotals::of(&composition, price_per_1k), Utc::now());
+        Some(composition)
+    }
+
+    /// Composition of the current UTC `period`
+    pub fn report(&self, period: BudgetPeriod) -> PromptCompositionReport {
+        self.ledger.lock().report(period, Utc::now(), &self.config)
+    }
+}
@@ -0,0 +1,151 @@
+//! Graders scoring a benchmark's output from 0.0 to 1.0
+
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+
+use hal9_core::{Error, Result};
+
+use crate::claude::ClaudeInterface;
+
+/// Highest score a judge gives; its score is divided by it
+pub const RUBRIC_SCALE: f64 = 10.0;
+
+/// How a benchmark's output is scored
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Grader {
+    /// 1.0 if the output, trimmed, is `expected`, otherwise 0.0
+    Exact {
+        expected: String,
+        #[serde(default = "default_case_sensitive")]
+        case_sensitive: bool,
+    },
+    /// The fraction of `patterns` the output matches; 0.0 if it matches any
+    /// of `forbidden`
+    Regex {
+        #[serde(default)]
+        patterns: Vec<String>,
+        #[serde(default)]
+        forbidden: Vec<String>,
+    },
+    /// A judge model's score of the output against `rubric`, out of
+    /// [`RUBRIC_SCALE`]
+    Rubric { rubric: String },
+}
+
+fn default_case_sensitive() -> bool {
+    true
+}
+
+/// A graded output
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Grade {
+    pub score: f64,
+    /// What was missed, or the judge's reasoning
+    pub detail: Option<String>,
+}
+
+impl Grade {
+    fn new(score: f64, detail: Option<String>) -> Self {
+        Self { score, detail }
+    }
+}
+
+impl Grader {
+    /// Check the grader can grade anything, giving the reason it cannot
+    pub fn check(&self) -> std::result::Result<(), String> {
+        match self {
+            Grader::Exact { .. } => Ok(()),
+            Grader::Regex { patterns, forbidden } => {
+                if patterns.is_empty() && forbidden.is_empty() {
+                    return Err("regex grader has no patterns".to_string());
+                }
+                for pattern in patterns.iter().chain(forbidden) {
+                    compile(pattern)?;
+                }
+                Ok(())
+            }
+            Grader::Rubric { rubric } if rubric.trim().is_empty() => Err("rubric is empty".to_string()),
+            Grader::Rubric { .. } => Ok(()),
+        }
+    }
+
+    /// Score `output`, the response to `input`. Rubric graders ask `judge`
+    /// and fail without one or when its reply carries no score.
+    pub async fn grade(&self, input: &str, output: &str, judge: Option<&dyn ClaudeInterface>) -> Result<Grade> {
+        let invalid = |message: String| Error::InvalidInput(format!("Invalid grader: {}", message));
+        match self {
+            Grader::Exact { expected, case_sensitive } => {
+                let (expected, actual) = (expected.trim(), output.trim());
+                let matched = if *case_sensitive {
+                    expected == actual
+                } else {
+                    expected.to_lowercase() == actual.to_lowercase()
+                };
+                let detail = (!matched).then(|| format!("Expected \"{}\"", expected));
+                Ok(Grade::new(if matched { 1.0 } else { 0.0 }, detail))
+            }
+            Grader::Regex { patterns, forbidden } => {
+                for pattern in forbidden {
+                    if compile(pattern).map_err(invalid)?.is_match(output) {
+                        return Ok(Grade::new(0.0, Some(format!("Matched forbidden /{}/", pattern))));
+                    }
+                }
+                let mut missed = Vec::new();
+                for pattern in patterns {
+                    if !compile(pattern).map_err(invalid)?.is_match(output) {
+                        missed.push(format!("/{}/", pattern));
+                    }
+                }
+                if patterns.is_empty() {
+                    return Ok(Grade::new(1.0, None));
+                }
+                let score = (patterns.len() - missed.len()) as f64 / patterns.len() as f64;
+                let detail = (!missed.is_empty()).then(|| format!("Missed {}", missed.join(", ")));
+                Ok(Grade::new(score, detail))
+            }
+            Grader::Rubric { rubric } => {
+                let judge = judge.ok_or_else(|| Error::Config("No judge to grade rubric benchmarks".to_string()))?;
+                let reply = judge.send_message(&judge_prompt(rubric, input, output)).await?;
+                parse_judgement(&reply)
+            }
+        }
+    }
+}
+
+fn compile(pattern: &str) -> std::result::Result<Regex, String> {
+    RegexBuilder::new(pattern)
+        .size_limit(1 << 20)
+        .build()
+        .map_err(|e| format!("invalid pattern /{}/: {}", pattern, e))
+}
+
+fn judge_prompt(rubric: &str, input: &str, output: &str) -> String {
+    format!(
+        "QUALITY_JUDGEMENT\nGrade the response to the input against the rubric.\n\nRUBRIC:\n{}\n\nINPUT:\n{}\n\nRESPONSE:\n{}\n\n\
+         Reply with SCORE: <0-{}> on its own line, followed by one line of reasoning.",
+        rubric, input, output, RUBRIC_SCALE
+    )
+}
+
+/// The `SCORE:` line of a judge's reply as a grade, the rest of the reply
+/// as its detail
+pub fn parse_judgement(reply: &str) -> Result<Grade> {
+    let unparseable = || Error::ClaudeApi(format!("Judge reply has no SCORE from 0 to {}: {}", RUBRIC_SCALE, reply.trim()));
+    let (line, value) = reply
+        .lines()
+        .find_map(|line| {
+            let trimmed = line.trim();
+            let prefix = trimmed.get(..6).filter(|prefix| prefix.eq_ignore_ascii_case("score:"))?;
+            Some((line, trimmed[prefix.len()..].trim()))
+        })
+        .ok_or_else(unparseable)?;
+    let value = value.split('/').next().unwrap_or_default().trim();
+    let score: f64 = value.parse().map_err(|_| unparseable())?;
+    if !(0.0..=RUBRIC_SCALE).contains(&score) {
+        return Err(unparseable());
+    }
+    let reasoning: Vec<&str> = reply.lines().filter(|l| *l != line && !l.trim().is_empty()).map(str::trim).collect();
+    let detail = (!reasoning.is_empty()).then(|| reasoning.join(" "));
+    Ok(Grade::new(score / RUBRIC_SCALE, detail))
+}
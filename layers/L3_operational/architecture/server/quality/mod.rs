@@ -0,0 +1,396 @@
+//! Result quality scoring and regression detection
+//!
+//! Benchmarks are curated signals, each sent to one neuron with a grader
+//! scoring the output from 0.0 to 1.0. A run scores every benchmark against
+//! one configuration of the neurons, built fresh with the server's neuron
+//! factory, and is tagged with the hash of that configuration and the model
+//! mapping. Runs happen on demand, when the server starts with a hash no
+//! run has scored yet (a deploy or a model remap), and after topology
+//! patches change benchmarked neurons.
+//!
+//! Trends compare the latest run with the one before it layer by layer,
+//! flagging significant drops (see [`trends`]). With `quality.gate` on, a
+//! topology patch changing benchmarked neurons, such as a new prompt, is
+//! first scored as a candidate run and refused if any of their layers
+//! regressed against the running configuration.
+
+pub mod graders;
+pub mod store;
+pub mod trends;
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tracing::{info, warn};
+
+use hal9_core::config::{DatabaseConfig, QualityConfig};
+use hal9_core::{Error, NeuronConfig, NeuronInterface, NeuronSignal, Result, ServerConfig};
+
+use crate::claude::ClaudeInterface;
+use crate::database_migrations;
+use crate::error::{ServerError, ServerResult};
+use crate::neuron::ManagedNeuron;
+use crate::self_organizer::NeuronFactory;
+
+pub use graders::{Grade, Grader};
+pub use store::{Benchmark, QualityRun, QualityStore, RunTrigger, Score};
+pub use trends::LayerTrend;
+
+/// Sender recorded on benchmark signals
+pub const BENCHMARK_SENDER: &str = "quality";
+
+/// Check the quality settings
+pub fn validate(config: &QualityConfig, database: &DatabaseConfig) -> Result<()> {
+    let invalid = |message: &str| Err(Error::Config(format!("Invalid quality config: {}", message)));
+    if config.enabled && database.url.is_none() {
+        return invalid("benchmarks are kept in the server database; set database.url");
+    }
+    if config.gate && !config.enabled {
+        return invalid("the gate needs quality.enabled");
+    }
+    if !(config.significance > 0.0 && config.significance <= 1.0) {
+        return invalid("significance must be in (0, 1]");
+    }
+    if !(0.0..=1.0).contains(&config.min_drop) {
+        return invalid("min_drop must be between 0 and 1");
+    }
+    Ok(())
+}
+
+/// A benchmark to add
+#[derive(Debug, Clone, Deserialize)]
+pub struct NewBenchmark {
+    pub name: String,
+    pub neuron: String,
+    pub input: String,
+    pub grader: Grader,
+}
+
+/// The latest run against the one before it
+#[derive(Debug, Clone, Serialize)]
+pub struct QualityTrends {
+    pub latest: Option<QualityRun>,
+    pub baseline: Option<QualityRun>,
+    pub layers: Vec<LayerTrend>,
+    /// Any layer regressed
+    pub regressed: bool,
+}
+
+/// What the gate found scoring a topology patch
+#[derive(Debug, Clone, Serialize)]
+pub struct GateReport {
+    pub passed: bool,
+    /// Run of the running configuration the candidate was compared with
+    pub baseline_run: Option<String>,
+    pub candidate_run: Option<String>,
+    /// Layers of the benchmarked neurons the patch changes
+    pub layers: Vec<LayerTrend>,
+}
+
+impl GateReport {
+    /// Why the gate refused, if it did
+    pub fn refusal(&self) -> Option<String> {
+        if self.passed {
+            return None;
+        }
+        let regressed: Vec<String> = self
+            .layers
+            .iter()
+            .filter(|trend| trend.regressed)
+            .map(|trend| format!("{} {:+.2} (p={:.3})", trend.layer, trend.delta, trend.p_value))
+            .collect();
+        Some(format!(
+            "Quality gate failed: {} regressed in run {}",
+            regressed.join(", "),
+            self.candidate_run.as_deref().unwrap_or("-")
+        ))
+    }
+}
+
+/// Benchmarks, their runs and the gate
+pub struct QualityHarness {
+    config: QualityConfig,
+    store: QualityStore,
+    judge: Box<dyn ClaudeInterface>,
+    model: String,
+    remap: BTreeMap<String, String>,
+    /// Held while a run is scoring
+    running: tokio::sync::Mutex<()>,
+}
+
+impl QualityHarness {
+    /// Connect to the server database if benchmarks are enabled; the
+    /// quality tables come with its migrations. `judge` grades rubric
+    /// benchmarks.
+    pub async fn open(config: &ServerConfig, judge: Box<dyn ClaudeInterface>) -> Result<Option<Self>> {
+        if !config.quality.enabled {
+            return Ok(None);
+        }
+        let pool = database_migrations::connect(&config.database)
+            .await?
+            .ok_or_else(|| Error::Config("Quality benchmarks need database.url".to_string()))?;
+        info!("Quality benchmarks kept in the {:?} database", pool.database_type());
+        Ok(Some(Self {
+            config: config.quality.clone(),
+            store: QualityStore::new(pool),
+            judge,
+            model: config.claude.model.clone(),
+            remap: config.models.remap.iter().map(|(from, to)| (from.clone(), to.clone())).collect(),
+            running: tokio::sync::Mutex::new(()),
+        }))
+    }
+
+    pub fn config(&self) -> &QualityConfig {
+        &self.config
+    }
+
+    /// Hash of `neurons`, in any order, with the model and its remapping
+    pub fn config_hash(&self, neurons: &[NeuronConfig]) -> String {
+        let mut neurons: Vec<&NeuronConfig> = neurons.iter().collect();
+        neurons.sort_by(|a, b| a.id.cmp(&b.id));
+        let value = serde_json::json!({ "neurons": neurons, "model": self.model, "remap": self.remap });
+        let digest = Sha256::digest(value.to_string().as_bytes());
+        digest[..8].iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    /// Add a benchmark for one of `neurons`
+    pub async fn add_benchmark(&self, new: NewBenchmark, neurons: &[NeuronConfig]) -> ServerResult<Benchmark> {
+        if new.name.trim().is_empty() || new.input.trim().is_empty() {
+            return Err(ServerError::InvalidInput("A benchmark needs a name and an input".to_string()));
+        }
+        if !neurons.iter().any(|neuron| neuron.id == new.neuron) {
+            return Err(ServerError::InvalidInput(format!("No neuron {}", new.neuron)));
+        }
+        new.grader.check().map_err(|e| ServerError::InvalidInput(format!("Invalid grader: {}", e)))?;
+
+        let benchmark = Benchmark {
+            id: uuid::Uuid::new_v4().to_string(),
+            name: new.name,
+            neuron: new.neuron,
+            input: new.input,
+            grader: new.grader,
+            created_at: Utc::now(),
+        };
+        self.store.insert_benchmark(&benchmark).await.map_err(internal)?;
+        info!("Quality benchmark {} added for neuron {}", benchmark.name, benchmark.neuron);
+        Ok(benchmark)
+    }
+
+    pub async fn benchmarks(&self) -> ServerResult<Vec<Benchmark>> {
+        self.store.benchmarks().await.map_err(internal)
+    }
+
+    pub async fn delete_benchmark(&self, id: &str) -> ServerResult<()> {
+        if !self.store.delete_benchmark(id).await.map_err(internal)? {
+            return Err(ServerError::NotFound(format!("No quality benchmark {}", id)));
+        }
+        Ok(())
+    }
+
+    /// Newest runs first, without their scores
+    pub async fn runs(&self) -> ServerResult<Vec<QualityRun>> {
+        self.store.runs().await.map_err(internal)
+    }
+
+    pub async fn run_details(&self, id: &str) -> ServerResult<QualityRun> {
+        self.store
+            .run(id)
+            .await
+            .map_err(internal)?
+            .ok_or_else(|| ServerError::NotFound(format!("No quality run {}", id)))
+    }
+
+    /// Whether a run of the running configuration should start: there are
+    /// benchmarks, and the latest run scored a different hash
+    pub async fn needs_run(&self, neurons: &[NeuronConfig]) -> Result<bool> {
+        if self.store.benchmarks().await?.is_empty() {
+            return Ok(false);
+        }
+        let latest = self.store.completed_runs(None, 1).await?;
+        Ok(latest.first().map(|run| run.config_hash.as_str()) != Some(self.config_hash(neurons).as_str()))
+    }
+
+    /// Score the benchmarks against `neurons`, built with `factory`; those
+    /// of `only` if given. A candidate run scores a configuration that is
+    /// not running. A judge failing ends the run with its error; a neuron
+    /// failing scores 0.0.
+    pub async fn run(
+        &self,
+        trigger: RunTrigger,
+        candidate: bool,
+        neurons: &[NeuronConfig],
+        factory: &NeuronFactory,
+        only: Option<&HashSet<String>>,
+    ) -> Result<QualityRun> {
+        let _running = self.running.lock().await;
+        let benchmarks: Vec<Benchmark> = self
+            .store
+            .benchmarks()
+            .await?
+            .into_iter()
+            .filter(|benchmark| only.is_none_or(|ids| ids.contains(&benchmark.neuron)))
+            .collect();
+
+        let mut run = QualityRun {
+            id: uuid::Uuid::new_v4().to_string(),
+            triggered_by: trigger,
+            config_hash: self.config_hash(neurons),
+            candidate,
+            started_at: Utc::now(),
+            finished_at: None,
+            mean_score: None,
+            error: None,
+            scores: Vec::new(),
+        };
+        self.store.insert_run(&run).await?;
+        info!(
+            "Quality run {} ({}) scoring {} benchmarks against {}",
+            run.id,
+            trigger.as_str(),
+            benchmarks.len(),
+            run.config_hash
+        );
+
+        let mut built: HashMap<String, ManagedNeuron> = HashMap::new();
+        for benchmark in &benchmarks {
+            let Some(config) = neurons.iter().find(|neuron| neuron.id == benchmark.neuron) else {
+                warn!("Quality benchmark {} skipped: no neuron {}", benchmark.name, benchmark.neuron);
+                continue;
+            };
+            match self.score(benchmark, config, factory, &mut built).await {
+                Ok(score) => {
+                    self.store.insert_score(&run.id, &score).await?;
+                    run.scores.push(score);
+                }
+                Err(e) => {
+                    run.error = Some(format!("Benchmark {}: {}", benchmark.name, e));
+                    break;
+                }
+            }
+        }
+        for neuron in built.values() {
+            if let Err(e) = neuron.shutdown().await {
+                warn!("Quality run {} failed to stop neuron {}: {}", run.id, neuron.id, e);
+            }
+        }
+
+        if run.error.is_none() && !run.scores.is_empty() {
+            run.mean_score = Some(run.scores.iter().map(|score| score.score).sum::<f64>() / run.scores.len() as f64);
+        }
+        run.finished_at = Some(Utc::now());
+        self.store.finish_run(&run).await?;
+        match &run.error {
+            Some(error) => warn!("Quality run {} failed: {}", run.id, error),
+            None => info!("Quality run {} scored {:.3}", run.id, run.mean_score.unwrap_or_default()),
+        }
+        Ok(run)
+    }
+
+    /// Score one benchmark, building its neuron on first use
+    async fn score(
+        &self,
+        benchmark: &Benchmark,
+        config: &NeuronConfig,
+        factory: &NeuronFactory,
+        built: &mut HashMap<String, ManagedNeuron>,
+    ) -> Result<Score> {
+        let scored = |score: f64, output: String, detail: Option<String>| Score {
+            benchmark_id: benchmark.id.clone(),
+            neuron: config.id.clone(),
+            layer: config.layer.clone(),
+            score,
+            output,
+            detail,
+        };
+        if !built.contains_key(&config.id) {
+            let neuron = match factory(config.clone()) {
+                Ok(neuron) => neuron,
+                Err(e) => return Ok(scored(0.0, String::new(), Some(format!("Neuron failed to build: {}", e)))),
+            };
+            neuron.start().await?;
+            built.insert(config.id.clone(), neuron);
+        }
+        let neuron = &built[&config.id];
+
+        let signal = NeuronSignal::forward(BENCHMARK_SENDER, &config.id, "API", &config.layer, benchmark.input.clone());
+        let output = match neuron.process_signal(&signal).await {
+            Ok(output) => output,
+            Err(e) => return Ok(scored(0.0, String::new(), Some(format!("Neuron failed: {}", e)))),
+        };
+        let grade = benchmark.grader.grade(&benchmark.input, &output, Some(self.judge.as_ref())).await?;
+        Ok(scored(grade.score, output, grade.detail))
+    }
+
+    /// The latest run of the running configuration against the one before
+    pub async fn trends(&self) -> ServerResult<QualityTrends> {
+        let mut runs = self.store.completed_runs(None, 2).await.map_err(internal)?;
+        let mut baseline = (runs.len() > 1).then(|| runs.remove(1));
+        let mut latest = runs.pop();
+        let layers = match (&baseline, &latest) {
+            (Some(baseline), Some(latest)) => self.compare(&baseline.scores, &latest.scores),
+            _ => Vec::new(),
+        };
+        for run in baseline.iter_mut().chain(latest.iter_mut()) {
+            run.scores.clear();
+        }
+        Ok(QualityTrends { regressed: layers.iter().any(|trend| trend.regressed), latest, baseline, layers })
+    }
+
+    fn compare(&self, baseline: &[Score], latest: &[Score]) -> Vec<LayerTrend> {
+        trends::compare(baseline, latest, self.config.significance, self.config.min_drop)
+    }
+
+    /// Score a topology change from `before` to `after` on the benchmarks
+    /// of the `changed` neurons, against the latest run of `before`,
+    /// running it first if it has not scored them all
+    pub async fn gate(
+        &self,
+        before: &[NeuronConfig],
+        after: &[NeuronConfig],
+        changed: &HashSet<String>,
+        factory: &NeuronFactory,
+    ) -> Result<GateReport> {
+        let affected: HashSet<String> = self
+            .store
+            .benchmarks()
+            .await?
+            .into_iter()
+            .filter(|benchmark| changed.contains(&benchmark.neuron))
+            .map(|benchmark| benchmark.id)
+            .collect();
+        if affected.is_empty() {
+            return Ok(GateReport { passed: true, baseline_run: None, candidate_run: None, layers: Vec::new() });
+        }
+
+        let hash = self.config_hash(before);
+        let stored = self.store.completed_runs(Some(&hash), 1).await?.pop();
+        let covers = |run: &QualityRun| {
+            let scored: HashSet<&str> = run.scores.iter().map(|score| score.benchmark_id.as_str()).collect();
+            affected.iter().all(|id| scored.contains(id.as_str()))
+        };
+        let baseline = match stored.filter(|run| covers(run)) {
+            Some(run) => run,
+            None => self.run(RunTrigger::Gate, false, before, factory, None).await?,
+        };
+        let candidate = self.run(RunTrigger::Gate, true, after, factory, Some(changed)).await?;
+        if let Some(error) = baseline.error.as_ref().or(candidate.error.as_ref()) {
+            return Err(Error::Processing(format!("Quality gate could not score the patch: {}", error)));
+        }
+
+        let baseline_scores: Vec<Score> =
+            baseline.scores.into_iter().filter(|score| affected.contains(&score.benchmark_id)).collect();
+        let layers = self.compare(&baseline_scores, &candidate.scores);
+        Ok(GateReport {
+            passed: !layers.iter().any(|trend| trend.regressed),
+            baseline_run: Some(baseline.id),
+            candidate_run: Some(candidate.id),
+            layers,
+        })
+    }
+}
+
+fn internal(e: Error) -> ServerError {
+    ServerError::Internal(e.to_string())
+}
@@ -0,0 +1,317 @@
+//! Benchmarks, runs and scores in the server database
+//!
+//! The tables come from the `005_quality_benchmarks` migration. Both
+//! backends take the same SQL; timestamps are Unix milliseconds.
+
+use chrono::{DateTime, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::Row;
+
+use hal9_core::{Error, Result};
+
+use super::graders::Grader;
+use crate::database::{on_pool, DatabasePool};
+
+/// Runs listed at most
+pub const MAX_LISTED_RUNS: i64 = 500;
+
+/// A curated signal and how its output is graded
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Benchmark {
+    pub id: String,
+    pub name: String,
+    /// Neuron the signal is sent to
+    pub neuron: String,
+    pub input: String,
+    pub grader: Grader,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Why benchmarks were run
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RunTrigger {
+    /// Asked for over the API
+    Manual,
+    /// The server started with a configuration not run against before
+    Deploy,
+    /// A topology patch changed neurons
+    TopologyChange,
+    /// The quality gate checked a topology patch
+    Gate,
+}
+
+impl RunTrigger {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RunTrigger::Manual => "manual",
+            RunTrigger::Deploy => "deploy",
+            RunTrigger::TopologyChange => "topology_change",
+            RunTrigger::Gate => "gate",
+        }
+    }
+
+    fn parse(value: &str) -> Self {
+        match value {
+            "deploy" => RunTrigger::Deploy,
+            "topology_change" => RunTrigger::TopologyChange,
+            "gate" => RunTrigger::Gate,
+            _ => RunTrigger::Manual,
+        }
+    }
+}
+
+/// A run of the benchmarks against one configuration
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QualityRun {
+    pub id: String,
+    pub triggered_by: RunTrigger,
+    /// Hash of the neurons and model mapping the run scored
+    pub config_hash: String,
+    /// Scored a proposed configuration rather than the running one; not
+    /// part of trends
+    pub candidate: bool,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+    /// Mean over the run's scores, once finished
+    pub mean_score: Option<f64>,
+    /// Why the run could not finish
+    pub error: Option<String>,
+    /// Per-benchmark scores; only on a single run
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub scores: Vec<Score>,
+}
+
+/// A benchmark's score in a run
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Score {
+    pub benchmark_id: String,
+    pub neuron: String,
+    /// Layer of the neuron when it was scored
+    pub layer: String,
+    pub score: f64,
+    pub output: String,
+    pub detail: Option<String>,
+}
+
+fn storage_error(e: sqlx::Error) -> Error {
+    Error::Storage(format!("Quality store: {}", e))
+}
+
+fn timestamp(millis: i64) -> DateTime<Utc> {
+    Utc.timestamp_millis_opt(millis).single().unwrap_or_default()
+}
+
+const RUN_COLUMNS: &str = "id, triggered_by, config_hash, candidate, started_at, finished_at, mean_score, error";
+
+macro_rules! run_from_row {
+    ($row:expr) => {
+        QualityRun {
+            id: $row.get("id"),
+            triggered_by: RunTrigger::parse(&$row.get::<String, _>("triggered_by")),
+            config_hash: $row.get("config_hash"),
+            candidate: $row.get("candidate"),
+            started_at: timestamp($row.get("started_at")),
+            finished_at: $row.get::<Option<i64>, _>("finished_at").map(timestamp),
+            mean_score: $row.get("mean_score"),
+            error: $row.get("error"),
+            scores: Vec::new(),
+        }
+    };
+}
+
+/// The quality tables
+pub struct QualityStore {
+    pool: DatabasePool,
+}
+
+impl QualityStore {
+    pub fn new(pool: DatabasePool) -> Self {
+        Self { pool }
+    }
+
+    pub fn pool(&self) -> &DatabasePool {
+        &self.pool
+    }
+
+    pub async fn insert_benchmark(&self, benchmark: &Benchmark) -> Result<()> {
+        let grader = serde_json::to_string(&benchmark.grader)?;
+        on_pool!(&self.pool, |pool| {
+            sqlx::query(
+                "INSERT INTO quality_benchmarks (id, name, neuron, input, grader, created_at) VALUES ($1, $2, $3, $4, $5, $6)",
+            )
+            .bind(&benchmark.id)
+            .bind(&benchmark.name)
+            .bind(&benchmark.neuron)
+            .bind(&benchmark.input)
+            .bind(&grader)
+            .bind(benchmark.created_at.timestamp_millis())
+            .execute(pool)
+            .await
+            .map_err(storage_error)?;
+        });
+        Ok(())
+    }
+
+    /// Every benchmark, oldest first
+    pub async fn benchmarks(&self) -> Result<Vec<Benchmark>> {
+        let query = "SELECT id, name, neuron, input, grader, created_at FROM quality_benchmarks ORDER BY created_at, id";
+        on_pool!(&self.pool, |pool| {
+            let rows = sqlx::query(query).fetch_all(pool).await.map_err(storage_error)?;
+            rows.iter()
+                .map(|row| -> Result<Benchmark> {
+                    Ok(Benchmark {
+                        id: row.get("id"),
+                        name: row.get("name"),
+                        neuron: row.get("neuron"),
+                        input: row.get("input"),
+                        grader: serde_json::from_str(&row.get::<String, _>("grader"))?,
+                        created_at: timestamp(row.get("created_at")),
+                    })
+                })
+                .collect()
+        })
+    }
+
+    /// Delete a benchmark, keeping its past scores; false if there was none
+    pub async fn delete_benchmark(&self, id: &str) -> Result<bool> {
+        let deleted = on_pool!(&self.pool, |pool| {
+            sqlx::query("DELETE FROM quality_benchmarks WHERE id = $1")
+                .bind(id)
+                .execute(pool)
+                .await
+                .map_err(storage_error)?
+                .rows_affected()
+        });
+        Ok(deleted > 0)
+    }
+
+    pub async fn insert_run(&self, run: &QualityRun) -> Result<()> {
+        on_pool!(&self.pool, |pool| {
+            sqlx::query(
+                "INSERT INTO quality_runs (id, triggered_by, config_hash, candidate, started_at) VALUES ($1, $2, $3, $4, $5)",
+            )
+            .bind(&run.id)
+            .bind(run.triggered_by.as_str())
+            .bind(&run.config_hash)
+            .bind(run.candidate)
+            .bind(run.started_at.timestamp_millis())
+            .execute(pool)
+            .await
+            .map_err(storage_error)?;
+        });
+        Ok(())
+    }
+
+    pub async fn insert_score(&self, run_id: &str, score: &Score) -> Result<()> {
+        on_pool!(&self.pool, |pool| {
+            sqlx::query(
+                r#"
+                INSERT INTO quality_scores (run_id, benchmark_id, neuron, layer, score, output, detail)
+                VALUES ($1, $2, $3, $4, $5, $6, $7)
+                "#,
+            )
+            .bind(run_id)
+            .bind(&score.benchmark_id)
+            .bind(&score.neuron)
+            .bind(&score.layer)
+            .bind(score.score)
+            .bind(&score.output)
+            .bind(&score.detail)
+            .execute(pool)
+            .await
+            .map_err(storage_error)?;
+        });
+        Ok(())
+    }
+
+    /// Record a run as finished, with its mean score or why it failed
+    pub async fn finish_run(&self, run: &QualityRun) -> Result<()> {
+        let finished_at = run.finished_at.unwrap_or_else(Utc::now).timestamp_millis();
+        on_pool!(&self.pool, |pool| {
+            sqlx::query("UPDATE quality_runs SET finished_at = $1, mean_score = $2, error = $3 WHERE id = $4")
+                .bind(finished_at)
+                .bind(run.mean_score)
+                .bind(&run.error)
+                .bind(&run.id)
+                .execute(pool)
+                .await
+                .map_err(storage_error)?;
+        });
+        Ok(())
+    }
+
+    /// Newest runs first, without their scores, at most
+    /// [`MAX_LISTED_RUNS`]
+    pub async fn runs(&self) -> Result<Vec<QualityRun>> {
+        let query = format!("SELECT {} FROM quality_runs ORDER BY started_at DESC LIMIT $1", RUN_COLUMNS);
+        on_pool!(&self.pool, |pool| {
+            let rows = sqlx::query(&query).bind(MAX_LISTED_RUNS).fetch_all(pool).await.map_err(storage_error)?;
+            Ok(rows.iter().map(|row| run_from_row!(row)).collect())
+        })
+    }
+
+    /// A run with its scores
+    pub async fn run(&self, id: &str) -> Result<Option<QualityRun>> {
+        let query = format!("SELECT {} FROM quality_runs WHERE id = $1", RUN_COLUMNS);
+        let run = on_pool!(&self.pool, |pool| {
+            let row = sqlx::query(&query).bind(id).fetch_optional(pool).await.map_err(storage_error)?;
+            row.map(|row| run_from_row!(row))
+        });
+        let Some(mut run) = run else {
+            return Ok(None);
+        };
+        run.scores = self.scores(id).await?;
+        Ok(Some(run))
+    }
+
+    /// The newest runs that were not of candidates and finished without
+    /// error, with their scores; of `config_hash` only if given
+    pub async fn completed_runs(&self, config_hash: Option<&str>, limit: i64) -> Result<Vec<QualityRun>> {
+        let query = format!(
+            r#"
+            SELECT {} FROM quality_runs
+            WHERE candidate = $1 AND finished_at IS NOT NULL AND error IS NULL AND ($2 IS NULL OR config_hash = $2)
+            ORDER BY started_at DESC
+            LIMIT $3
+            "#,
+            RUN_COLUMNS
+        );
+        let mut runs: Vec<QualityRun> = on_pool!(&self.pool, |pool| {
+            let rows = sqlx::query(&query)
+                .bind(false)
+                .bind(config_hash)
+                .bind(limit)
+                .fetch_all(pool)
+                .await
+                .map_err(storage_error)?;
+            rows.iter().map(|row| run_from_row!(row)).collect()
+        });
+        for run in &mut runs {
+            run.scores = self.scores(&run.id).await?;
+        }
+        Ok(runs)
+    }
+
+    async fn scores(&self, run_id: &str) -> Result<Vec<Score>> {
+        let query = r#"
+            SELECT benchmark_id, neuron, layer, score, output, detail FROM quality_scores
+            WHERE run_id = $1
+            ORDER BY layer, benchmark_id
+        "#;
+        on_pool!(&self.pool, |pool| {
+            let rows = sqlx::query(query).bind(run_id).fetch_all(pool).await.map_err(storage_error)?;
+            Ok(rows
+                .iter()
+                .map(|row| Score {
+                    benchmark_id: row.get("benchmark_id"),
+                    neuron: row.get("neuron"),
+                    layer: row.get("layer"),
+                    score: row.get("score"),
+                    output: row.get("output"),
+                    detail: row.get("detail"),
+                })
+                .collect())
+        })
+    }
+}
@@ -0,0 +1,118 @@
+//! Comparing two runs' scores layer by layer
+//!
+//! A layer's benchmarks scored in both runs are paired, and the mean of
+//! their differences tested with a one-sided sign-flip permutation test:
+//! if the change made no difference, each difference was as likely to
+//! come out negated, so the p-value is the share of sign assignments whose
+//! mean is at most the observed one. Up to [`EXACT_MAX`] pairs every
+//! assignment is counted; beyond, [`SAMPLES`] seeded ones are.
+
+use std::collections::{BTreeMap, HashMap};
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use super::store::Score;
+
+/// Pairs up to which every sign assignment is counted
+pub const EXACT_MAX: usize = 16;
+
+/// Sign assignments sampled for more pairs
+pub const SAMPLES: usize = 20_000;
+
+/// How a layer scored in one run against another
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LayerTrend {
+    pub layer: String,
+    /// Benchmarks scored in both runs
+    pub benchmarks: usize,
+    pub baseline_mean: f64,
+    pub latest_mean: f64,
+    pub delta: f64,
+    /// Chance of a drop at least this large were nothing to have changed
+    pub p_value: f64,
+    /// Dropped by at least the configured minimum, significantly
+    pub regressed: bool,
+}
+
+/// Compare `latest` scores against `baseline` per layer, flagging drops of
+/// at least `min_drop` with a p-value below `significance`
+pub fn compare(baseline: &[Score], latest: &[Score], significance: f64, min_drop: f64) -> Vec<LayerTrend> {
+    let before: HashMap<&str, f64> = baseline.iter().map(|s| (s.benchmark_id.as_str(), s.score)).collect();
+    let mut pairs: BTreeMap<&str, Vec<(f64, f64)>> = BTreeMap::new();
+    for score in latest {
+        if let Some(&previous) = before.get(score.benchmark_id.as_str()) {
+            pairs.entry(score.layer.as_str()).or_default().push((previous, score.score));
+        }
+    }
+
+    pairs
+        .into_iter()
+        .map(|(layer, pairs)| {
+            let n = pairs.len() as f64;
+            let baseline_mean = pairs.iter().map(|(before, _)| before).sum::<f64>() / n;
+            let latest_mean = pairs.iter().map(|(_, after)| after).sum::<f64>() / n;
+            let differences: Vec<f64> = pairs.iter().map(|(before, after)| after - before).collect();
+            let p_value = permutation_p_value(&differences);
+            let delta = latest_mean - baseline_mean;
+            LayerTrend {
+                layer: layer.to_string(),
+                benchmarks: pairs.len(),
+                baseline_mean,
+                latest_mean,
+                delta,
+                p_value,
+                regressed: -delta >= min_drop && p_value < significance,
+            }
+        })
+        .collect()
+}
+
+/// One-sided p-value of the mean of `differences` being this low by chance
+pub fn permutation_p_value(differences: &[f64]) -> f64 {
+    if differences.is_empty() {
+        return 1.0;
+    }
+    let observed: f64 = differences.iter().sum();
+    // Sums within rounding of the observed one count as reaching it
+    let reaches = |sum: f64| sum <= observed + 1e-9;
+
+    if differences.len() <= EXACT_MAX {
+        let assignments = 1u32 << differences.len();
+        let reaching = (0..assignments)
+            .filter(|signs| {
+                let sum = differences
+                    .iter()
+                    .enumerate()
+                    .map(|(i, d)| if signs & (1 << i) == 0 { *d } else { -d })
+                    .sum();
+                reaches(sum)
+            })
+            .count();
+        return reaching as f64 / assignments as f64;
+    }
+
+    let mut rng = StdRng::seed_from_u64(differences.len() as u64);
+    let reaching = (0..SAMPLES)
+        .filter(|_| {
+            let sum = differences.iter().map(|d| if rng.gen::<bool>() { *d } else { -d }).sum();
+            reaches(sum)
+        })
+        .count();
+    // The observed assignment counts as one of the samples
+    (reaching + 1) as f64 / (SAMPLES + 1) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_consistent_drops_are_significant_and_noise_is_not() {
+        assert_eq!(permutation_p_value(&[-0.5; 5]), 1.0 / 32.0);
+        assert_eq!(permutation_p_value(&[0.0; 4]), 1.0);
+        assert!(permutation_p_value(&[-0.5, 0.5, -0.25, 0.25]) > 0.5);
+        assert!(permutation_p_value(&[-0.2; 40]) < 0.001);
+    }
+}
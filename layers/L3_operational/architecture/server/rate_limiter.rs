@@ -1,4 +1,10 @@
 //! Rate limiting middleware for DDoS protection and API usage control
+//!
+//! Limits are enforced per instance by default. A limit class (per-IP or
+//! per-user) can instead be shared across every instance behind a load
+//! balancer through a [`CoordinationBackend`]. Shared counts are cached
+//! locally and pushed in the background, so an instance may admit up to
+//! `max_unsynced` requests per key beyond what the backend has seen.
 
 use axum::{
     extract::{Request, ConnectInfo},
@@ -6,15 +12,64 @@ use axum::{
     middleware::Next,
     response::{IntoResponse, Response},
 };
+use async_trait::async_trait;
 use std::{
     collections::HashMap,
     net::SocketAddr,
-    sync::Arc,
-    time::{Duration, Instant},
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, OnceLock,
+    },
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
-use tokio::sync::RwLock;
+use tokio::sync::{Mutex, RwLock};
 use tracing::{warn, info};
 
+use crate::metrics::Metrics;
+
+/// Longest a coordination backend call may take before the backend counts as down
+const BACKEND_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Whether a limit class is enforced per instance or across all instances
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LimitMode {
+    #[default]
+    Local,
+    Distributed,
+}
+
+impl FromStr for LimitMode {
+    type Err = String;
+    
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "local" => Ok(LimitMode::Local),
+            "distributed" => Ok(LimitMode::Distributed),
+            other => Err(format!("Unknown rate limit mode '{}', expected local or distributed", other)),
+        }
+    }
+}
+
+/// Rate limit classes, told apart by key
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LimitClass {
+    /// Keyed by client IP address
+    Ip,
+    /// Keyed by `user:<id>`
+    User,
+}
+
+impl LimitClass {
+    pub fn of(key: &str) -> Self {
+        if key.starts_with("user:") {
+            LimitClass::User
+        } else {
+            LimitClass::Ip
+        }
+    }
+}
+
 /// Rate limiter configuration
 #[derive(Debug, Clone)]
 pub struct RateLimitConfig {
@@ -26,6 +81,29 @@ pub struct RateLimitConfig {
     pub enabled: bool,
     /// Burst size for token bucket
     pub burst_size: u32,
+    /// Whether per-IP limits are shared across instances
+    pub ip_mode: LimitMode,
+    /// Whether per-user limits are shared across instances
+    pub user_mode: LimitMode,
+    /// Requests an instance may admit for a shared key before it has to
+    /// report them to the coordination backend
+    pub max_unsynced: u32,
+    /// How often local counts are pushed to the coordination backend
+    pub sync_interval: Duration,
+}
+
+impl RateLimitConfig {
+    pub fn mode(&self, class: LimitClass) -> LimitMode {
+        match class {
+            LimitClass::Ip => self.ip_mode,
+            LimitClass::User => self.user_mode,
+        }
+    }
+    
+    /// Whether any limit class needs a coordination backend
+    pub fn is_distributed(&self) -> bool {
+        self.ip_mode == LimitMode::Distributed || self.user_mode == LimitMode::Distributed
+    }
 }
 
 impl Default for RateLimitConfig {
@@ -35,6 +113,207 @@ impl Default for RateLimitConfig {
             window_duration: Duration::from_secs(60),
             enabled: true,
             burst_size: 10,
+            ip_mode: LimitMode::Local,
+            user_mode: LimitMode::Local,
+            max_unsynced: 5,
+            sync_interval: Duration::from_millis(250),
+        }
+    }
+}
+
+/// Coordination backend failure
+#[derive(Debug, thiserror::Error)]
+#[error("Rate limit coordination failed: {0}")]
+pub struct CoordinationError(pub String);
+
+/// Request counts shared by every instance
+#[async_trait]
+pub trait CoordinationBackend: Send + Sync {
+    /// Add `count` requests to `key` in fixed window `window` and return the
+    /// window's total across all instances. The count may expire after `ttl`.
+    async fn add(&self, key: &str, window: u64, count: u64, ttl: Duration) -> Result<u64, CoordinationError>;
+}
+
+/// Shared counts kept in Redis, one `INCRBY` per key and window
+pub struct RedisBackend {
+    client: redis::Client,
+    connection: Mutex<Option<redis::aio::ConnectionManager>>,
+    prefix: String,
+}
+
+impl RedisBackend {
+    /// Connects lazily, so a backend that is down at startup only degrades
+    /// limits until it comes up
+    pub fn new(url: &str) -> Result<Self, CoordinationError> {
+        Self::with_prefix(url, "hal9:ratelimit")
+    }
+    
+    pub fn with_prefix(url: &str, prefix: &str) -> Result<Self, CoordinationError> {
+        let client = redis::Client::open(url).map_err(|e| CoordinationError(e.to_string()))?;
+        Ok(Self {
+            client,
+            connection: Mutex::new(None),
+            prefix: prefix.to_string(),
+        })
+    }
+    
+    async fn connection(&self) -> Result<redis::aio::ConnectionManager, CoordinationError> {
+        let mut connection = self.connection.lock().await;
+        if let Some(connection) = connection.as_ref() {
+            return Ok(connection.clone());
+        }
+        let manager = redis::aio::ConnectionManager::new(self.client.clone())
+            .await
+            .map_err(|e| CoordinationError(e.to_string()))?;
+        *connection = Some(manager.clone());
+        Ok(manager)
+    }
+}
+
+#[async_trait]
+impl CoordinationBackend for RedisBackend {
+    async fn add(&self, key: &str, window: u64, count: u64, ttl: Duration) -> Result<u64, CoordinationError> {
+        let mut connection = self.connection().await?;
+        let key = format!("{}:{}:{}", self.prefix, key, window);
+        let (total,): (u64,) = redis::pipe()
+            .atomic()
+            .cmd("INCRBY").arg(&key).arg(count)
+            .cmd("PEXPIRE").arg(&key).arg(ttl.as_millis() as u64).ignore()
+            .query_async(&mut connection)
+            .await
+            .map_err(|e| CoordinationError(e.to_string()))?;
+        Ok(total)
+    }
+}
+
+/// A shared key's count in the current window
+#[derive(Debug)]
+struct SharedCount {
+    window: u64,
+    /// Window total across all instances as of the last report
+    synced: u64,
+    /// Requests admitted here and not yet acknowledged by the backend
+    unsynced: u64,
+}
+
+/// Fixed-window counts shared through a coordination backend
+struct SharedLimiter {
+    backend: Arc<dyn CoordinationBackend>,
+    counts: Mutex<HashMap<String, SharedCount>>,
+    /// Held while reporting, so an instance never runs more than
+    /// `max_unsynced` requests ahead of the backend
+    reporting: Mutex<()>,
+    limit: u64,
+    window_duration: Duration,
+    max_unsynced: u64,
+    degraded: AtomicBool,
+    metrics: OnceLock<Arc<Metrics>>,
+}
+
+impl SharedLimiter {
+    fn current_window(&self) -> (u64, Duration) {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+        let window_ms = self.window_duration.as_millis().max(1);
+        let window = now.as_millis() / window_ms;
+        let remaining = window_ms - now.as_millis() % window_ms;
+        (window as u64, Duration::from_millis(remaining as u64))
+    }
+    
+    fn is_degraded(&self) -> bool {
+        self.degraded.load(Ordering::Relaxed)
+    }
+    
+    fn set_degraded(&self, degraded: bool, reason: &str) {
+        if self.degraded.swap(degraded, Ordering::Relaxed) != degraded {
+            if degraded {
+                warn!("{}; enforcing distributed rate limits per instance", reason);
+            } else {
+                info!("Rate limit coordination recovered, enforcing shared limits");
+            }
+        }
+        if let Some(metrics) = self.metrics.get() {
+            metrics.set_rate_limit_degraded(degraded);
+        }
+    }
+    
+    /// Count a request against the shared limit, returning how long to wait
+    /// if it is reached, or `None` while degraded to local limits
+    async fn admit(&self, key: &str) -> Option<Result<(), Duration>> {
+        loop {
+            if self.is_degraded() {
+                return None;
+            }
+            let (window, remaining) = self.current_window();
+            {
+                let mut counts = self.counts.lock().await;
+                let count = counts.entry(key.to_string()).or_insert(SharedCount { window, synced: 0, unsynced: 0 });
+                if count.window != window {
+                    *count = SharedCount { window, synced: 0, unsynced: 0 };
+                }
+                if count.synced + count.unsynced >= self.limit {
+                    return Some(Err(remaining));
+                }
+                if count.unsynced < self.max_unsynced {
+                    count.unsynced += 1;
+                    return Some(Ok(()));
+                }
+            }
+            
+            // This instance has used its staleness allowance; report before admitting more
+            let _reporting = self.reporting.lock().await;
+            let pending = match self.counts.lock().await.get(key) {
+                Some(count) if count.window == window && count.unsynced >= self.max_unsynced => count.unsynced,
+                _ => continue,
+            };
+            self.report(key, window, pending).await;
+        }
+    }
+    
+    /// Count a request admitted by the local fallback, so the backend catches
+    /// up once it recovers
+    async fn record(&self, key: &str) {
+        let (window, _) = self.current_window();
+        let mut counts = self.counts.lock().await;
+        let count = counts.entry(key.to_string()).or_insert(SharedCount { window, synced: 0, unsynced: 0 });
+        if count.window != window {
+            *count = SharedCount { window, synced: 0, unsynced: 0 };
+        }
+        count.unsynced += 1;
+    }
+    
+    /// Push `pending` requests for `key` and take in the backend's total
+    async fn report(&self, key: &str, window: u64, pending: u64) {
+        let ttl = self.window_duration * 2;
+        match tokio::time::timeout(BACKEND_TIMEOUT, self.backend.add(key, window, pending, ttl)).await {
+            Ok(Ok(total)) => {
+                self.set_degraded(false, "");
+                if let Some(count) = self.counts.lock().await.get_mut(key) {
+                    if count.window == window {
+                        count.synced = count.synced.max(total);
+                        count.unsynced = count.unsynced.saturating_sub(pending);
+                    }
+                }
+            }
+            Ok(Err(e)) => self.set_degraded(true, &e.to_string()),
+            Err(_) => self.set_degraded(true, &format!("Rate limit coordination timed out after {:?}", BACKEND_TIMEOUT)),
+        }
+    }
+    
+    /// Report every key used this window, refreshing what other instances
+    /// have used, and drop keys from past windows
+    async fn sync(&self) {
+        let _reporting = self.reporting.lock().await;
+        let (window, _) = self.current_window();
+        let pending: Vec<(String, u64)> = {
+            let mut counts = self.counts.lock().await;
+            counts.retain(|_, count| count.window == window);
+            counts.iter().map(|(key, count)| (key.clone(), count.unsynced)).collect()
+        };
+        for (key, count) in pending {
+            self.report(&key, window, count).await;
+            if self.is_degraded() {
+                break;
+            }
         }
     }
 }
@@ -85,6 +364,7 @@ pub struct RateLimiter {
     config: RateLimitConfig,
     buckets: Arc<RwLock<HashMap<String, TokenBucket>>>,
     cleanup_interval: Duration,
+    shared: Option<Arc<SharedLimiter>>,
 }
 
 impl RateLimiter {
@@ -93,6 +373,7 @@ impl RateLimiter {
             config,
             buckets: Arc::new(RwLock::new(HashMap::new())),
             cleanup_interval: Duration::from_secs(300), // 5 minutes
+            shared: None,
         };
 
         // Start cleanup task
@@ -100,6 +381,50 @@ impl RateLimiter {
         
         limiter
     }
+    
+    /// A limiter whose distributed limit classes are shared through `backend`.
+    /// Shared keys are counted in fixed windows of `window_duration`.
+    pub fn with_backend(config: RateLimitConfig, backend: Arc<dyn CoordinationBackend>) -> Self {
+        let shared = Arc::new(SharedLimiter {
+            backend,
+            counts: Mutex::new(HashMap::new()),
+            reporting: Mutex::new(()),
+            limit: (config.max_requests + config.burst_size) as u64,
+            window_duration: config.window_duration,
+            max_unsynced: config.max_unsynced.max(1) as u64,
+            degraded: AtomicBool::new(false),
+            metrics: OnceLock::new(),
+        });
+        
+        let sync_interval = config.sync_interval;
+        let weak = Arc::downgrade(&shared);
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(sync_interval);
+            loop {
+                interval.tick().await;
+                let Some(shared) = weak.upgrade() else { break };
+                shared.sync().await;
+            }
+        });
+        
+        let mut limiter = Self::new(config);
+        limiter.shared = Some(shared);
+        limiter
+    }
+    
+    /// Report degradation to local-only limits through `metrics`
+    pub fn set_metrics(&self, metrics: Arc<Metrics>) {
+        if let Some(shared) = &self.shared {
+            metrics.set_rate_limit_degraded(shared.is_degraded());
+            let _ = shared.metrics.set(metrics);
+        }
+    }
+    
+    /// Whether distributed limits are currently enforced per instance
+    /// because the coordination backend is unreachable
+    pub fn is_degraded(&self) -> bool {
+        self.shared.as_ref().map(|shared| shared.is_degraded()).unwrap_or(false)
+    }
 
     /// Start background task to clean up old buckets
     fn start_cleanup_task(&self) {
@@ -134,6 +459,18 @@ impl RateLimiter {
         if !self.config.enabled {
             return Ok(());
         }
+        
+        let shared = self
+            .shared
+            .as_ref()
+            .filter(|_| self.config.mode(LimitClass::of(key)) == LimitMode::Distributed);
+        if let Some(shared) = shared {
+            if let Some(result) = shared.admit(key).await {
+                return result.map_err(|retry_after| RateLimitError::TooManyRequests {
+                    retry_after: retry_after.max(Duration::from_secs(1)),
+                });
+            }
+        }
 
         let mut buckets = self.buckets.write().await;
         
@@ -146,13 +483,17 @@ impl RateLimiter {
                 )
             });
 
-        if bucket.try_consume(1.0) {
-            Ok(())
-        } else {
-            Err(RateLimitError::TooManyRequests {
+        if !bucket.try_consume(1.0) {
+            return Err(RateLimitError::TooManyRequests {
                 retry_after: self.calculate_retry_after(bucket),
-            })
+            });
+        }
+        drop(buckets);
+        
+        if let Some(shared) = shared {
+            shared.record(key).await;
         }
+        Ok(())
     }
 
     /// Calculate retry-after duration
@@ -280,6 +621,7 @@ mod tests {
             window_duration: Duration::from_secs(1),
             enabled: true,
             burst_size: 0,
+            ..Default::default()
         };
         
         let limiter = RateLimiter::new(config);
@@ -303,6 +645,7 @@ mod tests {
             window_duration: Duration::from_secs(1),
             enabled: false,
             burst_size: 0,
+            ..Default::default()
         };
         
         let limiter = RateLimiter::new(config);
@@ -320,6 +663,7 @@ mod tests {
             window_duration: Duration::from_secs(1),
             enabled: true,
             burst_size: 5,
+            ..Default::default()
         };
         
         let limiter = RateLimiter::new(config);
@@ -0,0 +1,301 @@
+//! Read-only mode for maintenance windows
+//!
+//! While the server is read-only, the API refuses every request that could
+//! change state with a 503 `MAINTENANCE` error carrying a `retry_after` hint.
+//! Reads keep working, from each database's replica when one is configured
+//! in `read_only.replicas`. Chains already running finish, but their memory
+//! writes are skipped or held until the mode is turned off, as
+//! `read_only.memory_writes` says. Turning the mode off, draining,
+//! compaction and the adaptive timeout kill switch stay available, so an
+//! operator is never locked out.
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use axum::{
+    extract::{Request, State},
+    http::{header, HeaderValue, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::{DateTime, Utc};
+use parking_lot::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+use hal9_api_types::{ErrorCode, ErrorDetails, ErrorResponse};
+use hal9_core::{
+    config::{MemoryWritePolicy, ReadOnlyConfig},
+    memory::{MemoryEntry, MemoryNamespace, NamespacedMemory},
+    sqlite::SqlitePools,
+    Result,
+};
+
+/// Actor recorded when the mode is on from boot
+pub const CONFIG_ACTOR: &str = "config";
+
+/// Routes that still accept writes while read-only. Compaction changes no
+/// records, and a maintenance window is when it is best run. The adaptive
+/// timeout kill switch must work whenever timeouts misbehave, and so must
+/// clearing chaos injections and switching shadow traffic off.
+pub const CONTROL_PATHS: &[&str] = &[
+    "/api/v1/admin/readonly",
+    "/api/v1/admin/drain",
+    "/api/v1/admin/compact",
+    "/api/v1/admin/timeouts/adaptive",
+    "/api/v1/admin/chaos",
+    "/api/v1/admin/shadow",
+];
+
+/// Why and since when the server is read-only
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReadOnlyState {
+    pub reason: Option<String>,
+    /// Who turned the mode on, when known
+    pub enabled_by: Option<String>,
+    pub since: DateTime<Utc>,
+    pub retry_after_secs: u64,
+}
+
+/// Read-only mode as reported in status, health and metrics
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReadOnlyStatus {
+    pub active: bool,
+    #[serde(flatten)]
+    pub state: Option<ReadOnlyState>,
+    pub memory_writes: MemoryWritePolicy,
+    /// Memory writes waiting for the mode to be turned off
+    pub held_writes: usize,
+    /// Memory writes dropped while read-only, since startup
+    pub skipped_writes: u64,
+    /// API requests refused while read-only, since startup
+    pub refused_requests: u64,
+    /// Databases whose reads go to their replica
+    pub replica_reads: Vec<String>,
+}
+
+/// A neuron's write to a memory namespace
+pub struct MemoryWrite {
+    memory: Arc<NamespacedMemory>,
+    neuron_id: String,
+    layer: String,
+    namespace: MemoryNamespace,
+    entry: MemoryEntry,
+}
+
+impl MemoryWrite {
+    pub fn new(
+        memory: Arc<NamespacedMemory>,
+        neuron_id: &str,
+        layer: &str,
+        namespace: &MemoryNamespace,
+        entry: MemoryEntry,
+    ) -> Self {
+        Self {
+            memory,
+            neuron_id: neuron_id.to_string(),
+            layer: layer.to_string(),
+            namespace: namespace.clone(),
+            entry,
+        }
+    }
+
+    /// Write the entry, subject to the memory policy and namespace quotas
+    pub async fn apply(self) -> Result<Uuid> {
+        self.memory.write(&self.neuron_id, &self.layer, &self.namespace, self.entry).await
+    }
+}
+
+/// What a neuron should do with a memory write
+pub enum MemoryWriteAdmission {
+    /// The server is writable
+    Write(Box<MemoryWrite>),
+    /// Held until the mode is turned off; carries the entry's ID
+    Held(Uuid),
+    /// Dropped, by policy or because too many writes are held
+    Skipped,
+}
+
+/// Whether the server is read-only, and the memory writes held meanwhile
+pub struct ReadOnlyGate {
+    config: ReadOnlyConfig,
+    state: RwLock<Option<ReadOnlyState>>,
+    held: Mutex<VecDeque<MemoryWrite>>,
+    /// Databases whose reads switch to their replica, by name
+    databases: Mutex<Vec<(String, SqlitePools)>>,
+    skipped: AtomicU64,
+    refused: AtomicU64,
+}
+
+impl ReadOnlyGate {
+    /// A gate that is read-only from the start if `config.enabled`
+    pub fn new(config: ReadOnlyConfig) -> Self {
+        let state = config.enabled.then(|| {
+            warn!("Starting read-only: {}", config.reason.as_deref().unwrap_or("no reason given"));
+            ReadOnlyState {
+                reason: config.reason.clone(),
+                enabled_by: Some(CONFIG_ACTOR.to_string()),
+                since: Utc::now(),
+                retry_after_secs: config.retry_after_secs,
+            }
+        });
+        Self {
+            config,
+            state: RwLock::new(state),
+            held: Mutex::new(VecDeque::new()),
+            databases: Mutex::new(Vec::new()),
+            skipped: AtomicU64::new(0),
+            refused: AtomicU64::new(0),
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.state.read().is_some()
+    }
+
+    /// Why the server is read-only, if it is
+    pub fn state(&self) -> Option<ReadOnlyState> {
+        self.state.read().clone()
+    }
+
+    /// Make the server read-only. Turning the mode on again keeps its start
+    /// time and updates the reason and retry hint.
+    pub fn enable(&self, reason: Option<String>, retry_after_secs: Option<u64>, actor: Option<&str>) -> ReadOnlyState {
+        let mut state = self.state.write();
+        let since = state.as_ref().map_or_else(Utc::now, |state| state.since);
+        let enabled = ReadOnlyState {
+            reason,
+            enabled_by: actor.map(str::to_string),
+            since,
+            retry_after_secs: retry_after_secs.unwrap_or(self.config.retry_after_secs),
+        };
+        *state = Some(enabled.clone());
+        drop(state);
+
+        for (name, pools) in self.databases.lock().iter() {
+            if pools.set_replica_reads(true) {
+                info!("Reads of the {} database go to its replica", name);
+            }
+        }
+        warn!(
+            "Server is read-only (by {}): {}",
+            actor.unwrap_or("unknown"),
+            enabled.reason.as_deref().unwrap_or("no reason given")
+        );
+        enabled
+    }
+
+    /// Make the server writable again and write the held memory writes in
+    /// the order they were made. Returns how many were written.
+    pub async fn disable(&self, actor: Option<&str>) -> usize {
+        if self.state.write().take().is_none() {
+            return 0;
+        }
+        for (_, pools) in self.databases.lock().iter() {
+            pools.set_replica_reads(false);
+        }
+        info!("Server is writable again (by {})", actor.unwrap_or("unknown"));
+
+        let held: Vec<MemoryWrite> = self.held.lock().drain(..).collect();
+        let mut written = 0;
+        for write in held {
+            match write.apply().await {
+                Ok(_) => written += 1,
+                Err(e) => error!("Failed to write held memory: {}", e),
+            }
+        }
+        written
+    }
+
+    /// Track a database whose reads go to its replica while read-only,
+    /// attaching the replica configured for `name`
+    pub async fn register_database(&self, name: &str, pools: &SqlitePools) {
+        let Some(path) = self.config.replicas.get(name) else {
+            return;
+        };
+        let url = format!("sqlite:{}?mode=ro", path);
+        if let Err(e) = pools.attach_replica(&url).await {
+            warn!("{}; {} reads stay on the primary while read-only", e, name);
+            return;
+        }
+        pools.set_replica_reads(self.is_active());
+        self.databases.lock().push((name.to_string(), pools.clone()));
+    }
+
+    /// Let a memory write through, or hold or skip it while read-only
+    pub fn admit_memory_write(&self, write: MemoryWrite) -> MemoryWriteAdmission {
+        if !self.is_active() {
+            return MemoryWriteAdmission::Write(Box::new(write));
+        }
+        if self.config.memory_writes == MemoryWritePolicy::Hold {
+            let mut held = self.held.lock();
+            if held.len() < self.config.max_held_writes {
+                let id = write.entry.id;
+                held.push_back(write);
+                return MemoryWriteAdmission::Held(id);
+            }
+        }
+        self.skipped.fetch_add(1, Ordering::Relaxed);
+        MemoryWriteAdmission::Skipped
+    }
+
+    pub fn status(&self) -> ReadOnlyStatus {
+        let state = self.state();
+        ReadOnlyStatus {
+            active: state.is_some(),
+            state,
+            memory_writes: self.config.memory_writes,
+            held_writes: self.held.lock().len(),
+            skipped_writes: self.skipped.load(Ordering::Relaxed),
+            refused_requests: self.refused.load(Ordering::Relaxed),
+            replica_reads: self.databases.lock().iter()
+                .filter(|(_, pools)| pools.replica_reads())
+                .map(|(name, _)| name.clone())
+                .collect(),
+        }
+    }
+
+    /// The error returned for a refused request
+    fn refusal(&self, state: &ReadOnlyState) -> Response {
+        self.refused.fetch_add(1, Ordering::Relaxed);
+        let body = ErrorResponse {
+            error: ErrorDetails {
+                code: ErrorCode::Maintenance.to_string(),
+                message: format!(
+                    "Server is read-only for maintenance: {}",
+                    state.reason.as_deref().unwrap_or("writes are paused")
+                ),
+                error_id: None,
+                details: Some(serde_json::json!({
+                    "reason": state.reason,
+                    "since": state.since,
+                })),
+            },
+            retry_after: Some(state.retry_after_secs),
+            help_url: None,
+        };
+        let mut response = (StatusCode::SERVICE_UNAVAILABLE, Json(body)).into_response();
+        response.headers_mut().insert(header::RETRY_AFTER, HeaderValue::from(state.retry_after_secs));
+        response
+    }
+}
+
+/// Whether a request may change state; anything but GET, HEAD and OPTIONS
+/// outside [`CONTROL_PATHS`]
+pub fn is_mutating(method: &Method, path: &str) -> bool {
+    !matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS) && !CONTROL_PATHS.contains(&path)
+}
+
+/// Refuse mutating requests while read-only. Applied outside error
+/// recovery, which would replace the maintenance error with a generic one.
+pub async fn read_only_middleware(State(gate): State<Arc<ReadOnlyGate>>, req: Request, next: Next) -> Response {
+    if is_mutating(req.method(), req.uri().path()) {
+        if let Some(state) = gate.state() {
+            return gate.refusal(&state);
+        }
+    }
+    next.run(req).await
+}
@@ -0,0 +1,195 @@
+//! Verifiable receipts for finished chains
+//!
+//! A chain's receipt is issued the first time it is requested after the chain
+//! finishes: its steps are committed to a Merkle tree whose root the server
+//! signs (see [`hal9_core::receipt`]), then the root is handed to the
+//! configured [`Anchor`]. Issued receipts are kept, so signals that arrive
+//! after a cancellation do not change a receipt already handed out.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use chrono::Utc;
+use serde::Serialize;
+use tokio::sync::Mutex;
+use tracing::warn;
+
+use hal9_core::config::{AnchorConfig, ReceiptsConfig};
+use hal9_core::receipt::{AnchorRecord, ChainReceipt, InclusionProof, MerkleTree, ReceiptLeaf, ReceiptSigner};
+use hal9_core::{Error, Result};
+
+use crate::chain_tracker::{ChainRecord, ChainStatus, ChainStep};
+use crate::error::{ServerError, ServerResult};
+
+/// Publishes receipt roots somewhere third parties can check them
+#[async_trait]
+pub trait Anchor: Send + Sync {
+    fn name(&self) -> &str;
+
+    /// Publish the receipt's root, returning a reference to the published
+    /// record (e.g. a transaction hash), or `None` if nothing was published
+    async fn anchor(&self, receipt: &ChainReceipt) -> anyhow::Result<Option<String>>;
+}
+
+/// Anchor that publishes nothing; receipts rest on the server's signature alone
+pub struct LocalAnchor;
+
+#[async_trait]
+impl Anchor for LocalAnchor {
+    fn name(&self) -> &str {
+        "local"
+    }
+
+    async fn anchor(&self, _receipt: &ChainReceipt) -> anyhow::Result<Option<String>> {
+        Ok(None)
+    }
+}
+
+/// A signal's leaf with the path from it to the receipt's root
+#[derive(Debug, Clone, Serialize)]
+pub struct SignalProof {
+    pub leaf: ReceiptLeaf,
+    pub proof: InclusionProof,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReceiptResponse {
+    pub receipt: ChainReceipt,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proof: Option<SignalProof>,
+}
+
+struct IssuedReceipt {
+    receipt: ChainReceipt,
+    leaves: Vec<ReceiptLeaf>,
+    tree: MerkleTree,
+}
+
+/// Issues, anchors and keeps chain receipts
+pub struct ReceiptManager {
+    signer: ReceiptSigner,
+    anchor: Arc<dyn Anchor>,
+    /// Held across issuing so a chain is only signed and anchored once
+    issued: Mutex<HashMap<String, Arc<IssuedReceipt>>>,
+}
+
+impl ReceiptManager {
+    pub fn new(signer: ReceiptSigner, anchor: Arc<dyn Anchor>) -> Self {
+        Self {
+            signer,
+            anchor,
+            issued: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Build the signer and anchor from configuration
+    pub async fn from_config(config: &ReceiptsConfig) -> Result<Self> {
+        let signer = match &config.signing_key {
+            Some(key) => ReceiptSigner::from_hex(key.expose())?,
+            None => {
+                warn!("No receipts.signing_key configured; receipts are signed with a key that changes on restart");
+                ReceiptSigner::generate()
+            }
+        };
+
+        let anchor: Arc<dyn Anchor> = match &config.anchor {
+            AnchorConfig::Local => Arc::new(LocalAnchor),
+            #[cfg(feature = "blockchain")]
+            AnchorConfig::Ethereum { rpc_url, chain_id, private_key } => Arc::new(
+                crate::blockchain::EthereumAnchor::connect(rpc_url, *chain_id, private_key.expose())
+                    .await
+                    .map_err(|e| Error::Config(format!("receipts.anchor: {}", e)))?,
+            ),
+            #[cfg(not(feature = "blockchain"))]
+            AnchorConfig::Ethereum { .. } => {
+                return Err(Error::Config(
+                    "receipts.anchor type ethereum requires the blockchain feature".to_string(),
+                ))
+            }
+        };
+
+        Ok(Self::new(signer, anchor))
+    }
+
+    /// Hex key receipts from this server verify against
+    pub fn public_key(&self) -> String {
+        self.signer.public_key()
+    }
+
+    /// The receipt of a finished chain, with the proof for `signal_id` if given
+    pub async fn receipt(&self, record: &ChainRecord, signal_id: Option<&str>) -> ServerResult<ReceiptResponse> {
+        if record.status == ChainStatus::Running {
+            return Err(ServerError::InvalidInput(format!(
+                "Chain {} is still running",
+                record.chain_id
+            )));
+        }
+
+        let issued = self.issue(record).await;
+        let proof = match signal_id {
+            Some(signal_id) => {
+                let index = issued
+                    .leaves
+                    .iter()
+                    .position(|leaf| leaf.signal_id == signal_id)
+                    .ok_or_else(|| {
+                        ServerError::NotFound(format!("Signal {} is not in chain {}", signal_id, record.chain_id))
+                    })?;
+                Some(SignalProof {
+                    leaf: issued.leaves[index].clone(),
+                    proof: issued.tree.proof(index).expect("leaf index is in the tree"),
+                })
+            }
+            None => None,
+        };
+
+        Ok(ReceiptResponse {
+            receipt: issued.receipt.clone(),
+            proof,
+        })
+    }
+
+    async fn issue(&self, record: &ChainRecord) -> Arc<IssuedReceipt> {
+        let mut issued = self.issued.lock().await;
+        if let Some(existing) = issued.get(&record.chain_id) {
+            return existing.clone();
+        }
+
+        let leaves: Vec<ReceiptLeaf> = record.steps.iter().map(leaf_of).collect();
+        let tree = MerkleTree::new(&leaves);
+        let mut receipt = self.signer.sign(&record.chain_id, &tree);
+
+        // The receipt stands on its signature; a failed anchor is only logged
+        match self.anchor.anchor(&receipt).await {
+            Ok(Some(reference)) => {
+                receipt.anchor = Some(AnchorRecord {
+                    anchor: self.anchor.name().to_string(),
+                    reference,
+                    anchored_at: Utc::now(),
+                })
+            }
+            Ok(None) => {}
+            Err(e) => warn!("Failed to anchor receipt for chain {} with {}: {}", record.chain_id, self.anchor.name(), e),
+        }
+
+        let entry = Arc::new(IssuedReceipt { receipt, leaves, tree });
+        issued.insert(record.chain_id.clone(), entry.clone());
+        entry
+    }
+}
+
+/// The committed form of a chain step
+pub fn leaf_of(step: &ChainStep) -> ReceiptLeaf {
+    let content = step.output.as_deref().or(step.error.as_deref()).unwrap_or_default();
+    ReceiptLeaf {
+        signal_id: step.signal_id.clone(),
+        neuron_id: step.neuron_id.clone(),
+        layer: step.layer.clone(),
+        content_sha256: ReceiptLeaf::content_hash(content),
+        model: step.model.clone(),
+        prompt_tokens: step.prompt_tokens,
+        completion_tokens: step.completion_tokens,
+        timestamp_ms: step.timestamp.timestamp_millis(),
+    }
+}
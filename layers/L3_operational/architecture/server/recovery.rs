@@ -0,0 +1,410 @@
+//! Recovery playbooks run when a neuron fails a signal
+//!
+//! A playbook is an ordered list of steps, configured per layer in
+//! `ServerConfig.recovery` or per neuron under `settings.recovery`, e.g.
+//! retry with backoff, then a fallback model, then a sibling neuron, then a
+//! degraded answer, then the dead-letter queue. Steps are tried in order until
+//! one produces a response or parks the signal. Every attempted step and its
+//! outcome is written to the metadata of the signals that follow
+//! (`recovery.steps`, `recovery.outcome`, `recovery.error`), so a postmortem
+//! shows the path taken.
+//!
+//! Steps implement [`RecoveryStep`]; the playbook only sees the trait, so a
+//! new strategy needs no change to the executor.
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{info, warn};
+
+use hal9_core::{
+    config::{RecoveryPlaybookConfig, RecoveryStepConfig},
+    metadata_schema::keys,
+    Error, NeuronConfig, NeuronInterface, NeuronSignal, Result,
+};
+
+use crate::{
+    claude::ClaudeInterface,
+    concurrency::{Admission, DeadLetterQueue},
+    neuron::ManagedNeuron,
+};
+
+/// Signal metadata key holding the attempted steps as `step:outcome` pairs
+pub const RECOVERY_STEPS_KEY: &str = keys::recovery::STEPS;
+/// Signal metadata key holding how recovery ended
+pub const RECOVERY_OUTCOME_KEY: &str = keys::recovery::OUTCOME;
+/// Signal metadata key holding the error that started recovery
+pub const RECOVERY_ERROR_KEY: &str = keys::recovery::ERROR;
+
+/// What recovery of a failed signal can draw on
+pub struct RecoveryContext<'a> {
+    pub signal: &'a NeuronSignal,
+    /// Neuron that failed the signal
+    pub neuron: &'a ManagedNeuron,
+    /// Error that started recovery
+    pub error: &'a str,
+    /// Other neurons serving the same role: the failed neuron's clones, or
+    /// the neuron it was cloned from and its other clones
+    pub siblings: Vec<Arc<ManagedNeuron>>,
+    pub dead_letters: &'a DeadLetterQueue,
+}
+
+/// How one step ended
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StepOutcome {
+    /// A full response; the playbook ends
+    Recovered(String),
+    /// A best-effort response, forwarded marked as degraded; the playbook ends
+    Degraded(String),
+    /// The step ran without producing a response
+    Failed(String),
+    /// The step did not apply, e.g. there are no siblings
+    Skipped(String),
+    /// The signal was moved to the dead-letter queue; the playbook ends
+    DeadLettered,
+}
+
+impl StepOutcome {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StepOutcome::Recovered(_) => "recovered",
+            StepOutcome::Degraded(_) => "degraded",
+            StepOutcome::Failed(_) => "failed",
+            StepOutcome::Skipped(_) => "skipped",
+            StepOutcome::DeadLettered => "dead_lettered",
+        }
+    }
+}
+
+/// A recovery strategy
+#[async_trait]
+pub trait RecoveryStep: Send + Sync {
+    /// Name used in signal metadata and metrics
+    fn name(&self) -> &'static str;
+
+    /// Try to recover the signal. `trail` holds the steps attempted before
+    /// this one.
+    async fn attempt(&self, ctx: &RecoveryContext<'_>, trail: &[StepRecord]) -> StepOutcome;
+}
+
+/// One attempted step
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StepRecord {
+    pub step: String,
+    pub outcome: String,
+}
+
+impl StepRecord {
+    fn new(step: &str, outcome: &StepOutcome) -> Self {
+        Self { step: step.to_string(), outcome: outcome.as_str().to_string() }
+    }
+}
+
+/// How recovery ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecoveryOutcome {
+    Recovered,
+    Degraded,
+    DeadLettered,
+    /// Every step was tried without success
+    Exhausted,
+}
+
+impl RecoveryOutcome {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RecoveryOutcome::Recovered => "recovered",
+            RecoveryOutcome::Degraded => "degraded",
+            RecoveryOutcome::DeadLettered => "dead_lettered",
+            RecoveryOutcome::Exhausted => "exhausted",
+        }
+    }
+}
+
+/// Path one failed signal took through its playbook
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecoveryReport {
+    pub error: String,
+    pub steps: Vec<StepRecord>,
+    pub outcome: RecoveryOutcome,
+    /// Response of the step that recovered or degraded the signal
+    pub response: Option<String>,
+}
+
+impl RecoveryReport {
+    /// Write the report into signal metadata
+    pub fn apply_to(&self, metadata: &mut HashMap<String, String>) {
+        write_trail(metadata, &self.error, &self.steps, self.outcome);
+    }
+}
+
+fn write_trail(metadata: &mut HashMap<String, String>, error: &str, steps: &[StepRecord], outcome: RecoveryOutcome) {
+    let trail = steps.iter()
+        .map(|record| format!("{}:{}", record.step, record.outcome))
+        .collect::<Vec<_>>()
+        .join(",");
+    metadata.insert(RECOVERY_STEPS_KEY.to_string(), trail);
+    metadata.insert(RECOVERY_OUTCOME_KEY.to_string(), outcome.as_str().to_string());
+    metadata.insert(RECOVERY_ERROR_KEY.to_string(), error.to_string());
+}
+
+/// Process the signal again on the same neuron, doubling the wait before
+/// each attempt up to `max_backoff`
+pub struct RetryStep {
+    attempts: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+}
+
+impl RetryStep {
+    pub fn new(attempts: u32, initial_backoff: Duration, max_backoff: Duration) -> Self {
+        Self { attempts, initial_backoff, max_backoff }
+    }
+}
+
+#[async_trait]
+impl RecoveryStep for RetryStep {
+    fn name(&self) -> &'static str {
+        "retry"
+    }
+
+    async fn attempt(&self, ctx: &RecoveryContext<'_>, _trail: &[StepRecord]) -> StepOutcome {
+        let mut backoff = self.initial_backoff;
+        let mut last_error = ctx.error.to_string();
+        for _ in 0..self.attempts {
+            tokio::time::sleep(backoff).await;
+            match ctx.neuron.process_signal(ctx.signal).await {
+                Ok(response) => return StepOutcome::Recovered(response),
+                Err(e) => last_error = e.to_string(),
+            }
+            backoff = (backoff * 2).min(self.max_backoff);
+        }
+        StepOutcome::Failed(last_error)
+    }
+}
+
+/// Send the neuron's prompt to another model. The fallback answers without
+/// tools or validation.
+pub struct FallbackModelStep {
+    claude: Box<dyn ClaudeInterface>,
+}
+
+impl FallbackModelStep {
+    pub fn new(claude: Box<dyn ClaudeInterface>) -> Self {
+        Self { claude }
+    }
+}
+
+#[async_trait]
+impl RecoveryStep for FallbackModelStep {
+    fn name(&self) -> &'static str {
+        "fallback_model"
+    }
+
+    async fn attempt(&self, ctx: &RecoveryContext<'_>, _trail: &[StepRecord]) -> StepOutcome {
+        let prompt = ctx.neuron.recovery_prompt(ctx.signal).await;
+        match self.claude.send_message(&prompt).await {
+            Ok(response) => StepOutcome::Recovered(response),
+            Err(e) => StepOutcome::Failed(e.to_string()),
+        }
+    }
+}
+
+/// Process the signal on each sibling in turn; siblings with a full queue
+/// are passed over
+pub struct RerouteSiblingStep;
+
+#[async_trait]
+impl RecoveryStep for RerouteSiblingStep {
+    fn name(&self) -> &'static str {
+        "reroute_sibling"
+    }
+
+    async fn attempt(&self, ctx: &RecoveryContext<'_>, _trail: &[StepRecord]) -> StepOutcome {
+        if ctx.siblings.is_empty() {
+            return StepOutcome::Skipped(format!("Neuron {} has no siblings", ctx.neuron.id()));
+        }
+
+        let mut failures = Vec::new();
+        for sibling in &ctx.siblings {
+            let _permit = match sibling.admit().await {
+                Admission::Admitted(permit) => permit,
+                Admission::Rejected | Admission::Shed(_) => {
+                    failures.push(format!("{}: queue is full", sibling.id()));
+                    continue;
+                }
+            };
+            match sibling.process_signal(ctx.signal).await {
+                Ok(response) => return StepOutcome::Recovered(response),
+                Err(e) => failures.push(format!("{}: {}", sibling.id(), e)),
+            }
+        }
+        StepOutcome::Failed(failures.join("; "))
+    }
+}
+
+/// Ask a model for a short best-effort answer, forwarded marked as degraded
+pub struct SummarizeAndDegradeStep {
+    claude: Box<dyn ClaudeInterface>,
+    max_chars: usize,
+}
+
+impl SummarizeAndDegradeStep {
+    pub fn new(claude: Box<dyn ClaudeInterface>, max_chars: usize) -> Self {
+        Self { claude, max_chars }
+    }
+
+    fn prompt(&self, ctx: &RecoveryContext<'_>) -> String {
+        // The request may itself be why processing failed, so it is cut short
+        let request: String = ctx.signal.payload.activation.content.chars().take(self.max_chars * 4).collect();
+        format!(
+            "Processing the request below failed: {}\n\nGive a brief best-effort answer in at most {} characters, \
+             and say what could not be done.\n\nREQUEST:\n{}",
+            ctx.error, self.max_chars, request
+        )
+    }
+}
+
+#[async_trait]
+impl RecoveryStep for SummarizeAndDegradeStep {
+    fn name(&self) -> &'static str {
+        "summarize_and_degrade"
+    }
+
+    async fn attempt(&self, ctx: &RecoveryContext<'_>, _trail: &[StepRecord]) -> StepOutcome {
+        match self.claude.send_message(&self.prompt(ctx)).await {
+            Ok(summary) => StepOutcome::Degraded(summary.chars().take(self.max_chars).collect()),
+            Err(e) => StepOutcome::Failed(e.to_string()),
+        }
+    }
+}
+
+/// Move the signal, with its recovery trail, to the dead-letter queue
+pub struct DeadLetterStep;
+
+#[async_trait]
+impl RecoveryStep for DeadLetterStep {
+    fn name(&self) -> &'static str {
+        "dead_letter"
+    }
+
+    async fn attempt(&self, ctx: &RecoveryContext<'_>, trail: &[StepRecord]) -> StepOutcome {
+        let outcome = StepOutcome::DeadLettered;
+        let mut steps = trail.to_vec();
+        steps.push(StepRecord::new(self.name(), &outcome));
+
+        let mut signal = ctx.signal.clone();
+        write_trail(&mut signal.metadata, ctx.error, &steps, RecoveryOutcome::DeadLettered);
+        ctx.dead_letters.push(ctx.neuron.id(), "recovery_exhausted", signal);
+        outcome
+    }
+}
+
+/// Recovery steps configured for one neuron
+pub struct RecoveryPlaybook {
+    steps: Vec<Box<dyn RecoveryStep>>,
+}
+
+impl RecoveryPlaybook {
+    pub fn new(steps: Vec<Box<dyn RecoveryStep>>) -> Self {
+        Self { steps }
+    }
+
+    /// Build the configured steps; `claude_for` creates the client of a
+    /// model-backed step from its model name
+    pub fn from_config(
+        config: &RecoveryPlaybookConfig,
+        claude_for: &dyn Fn(&str) -> Result<Box<dyn ClaudeInterface>>,
+    ) -> Result<Self> {
+        let steps = config
+            .steps
+            .iter()
+            .map(|step| -> Result<Box<dyn RecoveryStep>> {
+                Ok(match step {
+                    RecoveryStepConfig::Retry { attempts, initial_backoff_ms, max_backoff_ms } => Box::new(RetryStep::new(
+                        *attempts,
+                        Duration::from_millis(*initial_backoff_ms),
+                        Duration::from_millis(*max_backoff_ms),
+                    )),
+                    RecoveryStepConfig::FallbackModel { model } => Box::new(FallbackModelStep::new(claude_for(model)?)),
+                    RecoveryStepConfig::RerouteSibling => Box::new(RerouteSiblingStep),
+                    RecoveryStepConfig::SummarizeAndDegrade { model, max_chars } => {
+                        Box::new(SummarizeAndDegradeStep::new(claude_for(model)?, *max_chars))
+                    }
+                    RecoveryStepConfig::DeadLetter => Box::new(DeadLetterStep),
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self::new(steps))
+    }
+
+    /// Playbook for a neuron: its `settings.recovery` if present, else its
+    /// layer's entry in `layers`. `None` when no steps apply.
+    pub fn for_neuron(
+        layers: &HashMap<String, RecoveryPlaybookConfig>,
+        neuron: &NeuronConfig,
+        claude_for: &dyn Fn(&str) -> Result<Box<dyn ClaudeInterface>>,
+    ) -> Result<Option<Self>> {
+        let config = match neuron.settings.get("recovery") {
+            Some(settings) => serde_json::from_value::<RecoveryPlaybookConfig>(settings.clone()).map_err(|e| {
+                Error::Config(format!("Invalid recovery settings for neuron {}: {}", neuron.id, e))
+            })?,
+            None => match layers.get(&neuron.layer) {
+                Some(config) => config.clone(),
+                None => return Ok(None),
+            },
+        };
+        if config.steps.is_empty() {
+            return Ok(None);
+        }
+        Self::from_config(&config, claude_for).map(Some)
+    }
+
+    /// Step names, in order
+    pub fn step_names(&self) -> Vec<&'static str> {
+        self.steps.iter().map(|step| step.name()).collect()
+    }
+
+    /// Try each step in order until one recovers, degrades or dead-letters
+    /// the signal
+    pub async fn run(&self, ctx: &RecoveryContext<'_>) -> RecoveryReport {
+        let mut steps = Vec::new();
+        for step in &self.steps {
+            let outcome = step.attempt(ctx, &steps).await;
+            if let Some(metrics) = ctx.neuron.metrics() {
+                metrics.record_recovery_step(step.name(), outcome.as_str());
+            }
+            match &outcome {
+                StepOutcome::Failed(reason) | StepOutcome::Skipped(reason) => warn!(
+                    target: "neuron.recovery",
+                    neuron_id = %ctx.neuron.id(),
+                    signal_id = %ctx.signal.signal_id,
+                    step = step.name(),
+                    outcome = outcome.as_str(),
+                    "Recovery step did not help: {}", reason
+                ),
+                _ => info!(
+                    target: "neuron.recovery",
+                    neuron_id = %ctx.neuron.id(),
+                    signal_id = %ctx.signal.signal_id,
+                    step = step.name(),
+                    outcome = outcome.as_str(),
+                    "Recovery step succeeded"
+                ),
+            }
+            steps.push(StepRecord::new(step.name(), &outcome));
+
+            let (result, response) = match outcome {
+                StepOutcome::Recovered(response) => (RecoveryOutcome::Recovered, Some(response)),
+                StepOutcome::Degraded(response) => (RecoveryOutcome::Degraded, Some(response)),
+                StepOutcome::DeadLettered => (RecoveryOutcome::DeadLettered, None),
+                StepOutcome::Failed(_) | StepOutcome::Skipped(_) => continue,
+            };
+            return RecoveryReport { error: ctx.error.to_string(), steps, outcome: result, response };
+        }
+        RecoveryReport { error: ctx.error.to_string(), steps, outcome: RecoveryOutcome::Exhausted, response: None }
+    }
+}
@@ -0,0 +1,424 @@
+//! Differential privacy for exported usage reports
+//!
+//! Usage and tag spend reports are released through the Laplace mechanism,
+//! never as is. Every count and sum in one gets noise of scale
+//! `sensitivity / ε'`, where the query's epsilon (its `?dp_epsilon=`, else
+//! the configured default) is split evenly over the statistics released
+//! (`ε' = ε / statistics`), so the whole report costs `ε`. Sensitivities
+//! come from [`ReportPrivacyConfig`]: one for counts, and the most tokens or
+//! cost one chain is taken to add.
+//!
+//! Each API key has a total epsilon it may spend; a query that would go past
+//! it is refused before anything is computed. Spent epsilon is kept in the
+//! `privacy_budgets` table of the auth database, so it survives restarts,
+//! and callers who are not authenticated get no reports at all.
+//!
+//! Noised counts below the suppression threshold are withheld: a report
+//! field becomes `null`, and a row of a list (a tag group or combination) is
+//! left out. Per-user rows, ratios and period comparisons, which would be
+//! computed from unnoised values, are left out of noised reports entirely.
+//!
+//! This is a transform of the report only; recorded usage and the cached
+//! report are untouched. Statistics are noised independently, so totals no
+//! longer equal the sum of their rows.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::OnceLock;
+
+use chrono::Utc;
+use parking_lot::Mutex;
+use rand::Rng;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use sqlx::Row;
+
+use hal9_core::config::ReportPrivacyConfig;
+use hal9_core::sqlite::SqlitePools;
+
+use crate::{auth_middleware::AuthUser, error::{ServerError, ServerResult}};
+
+/// Fields left out of noised reports
+pub const WITHHELD_FIELDS: &[&str] = &["top_users", "webhook_success_rate", "avg_chain_latency_ms", "comparison"];
+
+/// What a released statistic measures, which fixes its sensitivity
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum StatKind {
+    Count,
+    Tokens,
+    CostUsd,
+}
+
+impl StatKind {
+    /// Kind of a report field, by its name
+    fn of(field: &str) -> Option<Self> {
+        match field {
+            "chains" | "chains_started" | "chains_completed" | "chains_failed" | "signals"
+            | "webhook_deliveries" | "dead_letters" => Some(Self::Count),
+            "tokens" | "prompt_tokens" | "completion_tokens" => Some(Self::Tokens),
+            "cost_usd" => Some(Self::CostUsd),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Count => "count",
+            Self::Tokens => "tokens",
+            Self::CostUsd => "cost_usd",
+        }
+    }
+}
+
+/// Noise added to one kind of statistic
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct NoiseScale {
+    /// Most one chain changes the statistic by
+    pub sensitivity: f64,
+    /// Laplace scale `b`; the noise has standard deviation `b·√2`
+    pub scale: f64,
+}
+
+/// How a noised report was released, returned with it
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct NoiseParameters {
+    pub mechanism: &'static str,
+    /// Epsilon of the whole query
+    pub epsilon: f64,
+    /// Counts and sums released, which share `epsilon` evenly
+    pub statistics: usize,
+    pub epsilon_per_statistic: f64,
+    /// By `count`, `tokens` and `cost_usd`
+    pub noise: BTreeMap<&'static str, NoiseScale>,
+    pub suppression_threshold: u64,
+    /// Counts withheld for falling below the threshold
+    pub suppressed: usize,
+    /// Fields left out of the report
+    pub withheld: Vec<&'static str>,
+    /// Epsilon the caller has spent, this query included
+    pub epsilon_spent: f64,
+    pub epsilon_remaining: f64,
+}
+
+/// A report with noised counts and sums
+#[derive(Debug, Clone, Serialize)]
+pub struct PrivateReport {
+    #[serde(flatten)]
+    pub report: Map<String, Value>,
+    pub privacy: NoiseParameters,
+}
+
+/// A sample of the Laplace distribution centred on zero with scale `scale`
+pub fn laplace<R: Rng + ?Sized>(rng: &mut R, scale: f64) -> f64 {
+    let u: f64 = rng.gen::<f64>() - 0.5;
+    -scale * u.signum() * (1.0 - 2.0 * u.abs()).max(f64::MIN_POSITIVE).ln()
+}
+
+/// Whose privacy budget a query is charged to: the API key it was made
+/// with, else the user, within their organization if they have one
+pub fn budget_key(user: &AuthUser) -> String {
+    let caller = match &user.api_key_id {
+        Some(key) => format!("key:{}", key),
+        None => format!("user:{}", user.user_id),
+    };
+    match &user.org_id {
+        Some(org_id) => format!("org:{}/{}", org_id, caller),
+        None => caller,
+    }
+}
+
+/// The caller a report is charged to; anonymous callers get none
+pub fn require_caller(user: Option<&AuthUser>) -> ServerResult<String> {
+    user.map(budget_key).ok_or_else(|| {
+        ServerError::Forbidden("Usage reports are charged to a privacy budget and need an authenticated caller".to_string())
+    })
+}
+
+fn storage_error(e: sqlx::Error) -> hal9_core::Error {
+    hal9_core::Error::Storage(format!("Privacy budgets: {}", e))
+}
+
+/// The `privacy_budgets` table: epsilon spent by each caller of
+/// [`budget_key`]. Timestamps are Unix milliseconds.
+pub struct PrivacyBudgetStore {
+    pools: SqlitePools,
+}
+
+impl PrivacyBudgetStore {
+    pub fn new(pools: SqlitePools) -> Self {
+        Self { pools }
+    }
+
+    /// Create the privacy budgets table
+    pub async fn initialize(&self) -> hal9_core::Result<()> {
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS privacy_budgets (
+                caller TEXT PRIMARY KEY,
+                epsilon_spent REAL NOT NULL,
+                updated_at INTEGER NOT NULL
+            )
+            "#,
+        )
+        .execute(self.pools.writer())
+        .await
+        .map_err(storage_error)?;
+        Ok(())
+    }
+
+    /// Add `epsilon` to what `caller` has spent
+    pub async fn add(&self, caller: &str, epsilon: f64) -> hal9_core::Result<()> {
+        let now = Utc::now().timestamp_millis();
+        self.pools
+            .write(|pool| async move {
+                sqlx::query(
+                    "INSERT INTO privacy_budgets (caller, epsilon_spent, updated_at) VALUES ($1, $2, $3) \
+                     ON CONFLICT(caller) DO UPDATE SET epsilon_spent = epsilon_spent + excluded.epsilon_spent, updated_at = excluded.updated_at",
+                )
+                .bind(caller)
+                .bind(epsilon)
+                .bind(now)
+                .execute(&pool)
+                .await
+            })
+            .await
+            .map_err(storage_error)?;
+        Ok(())
+    }
+
+    /// Epsilon spent, by caller
+    pub async fn load(&self) -> hal9_core::Result<HashMap<String, f64>> {
+        let rows = sqlx::query("SELECT caller, epsilon_spent FROM privacy_budgets")
+            .fetch_all(self.pools.reader())
+            .await
+            .map_err(storage_error)?;
+        Ok(rows.into_iter().map(|row| (row.get("caller"), row.get("epsilon_spent"))).collect())
+    }
+}
+
+/// Releases noised reports and tracks the epsilon each caller has spent
+pub struct ReportPrivacy {
+    config: ReportPrivacyConfig,
+    spent: Mutex<HashMap<String, f64>>,
+    /// Where spent epsilon is kept, once attached
+    store: OnceLock<PrivacyBudgetStore>,
+}
+
+impl ReportPrivacy {
+    pub fn new(config: ReportPrivacyConfig) -> Self {
+        Self { config, spent: Mutex::new(HashMap::new()), store: OnceLock::new() }
+    }
+
+    /// Keep spent epsilon in `pools`, picking up what was spent before
+    pub async fn attach_store(&self, pools: SqlitePools) -> hal9_core::Result<()> {
+        let store = PrivacyBudgetStore::new(pools);
+        store.initialize().await?;
+        let spent = store.load().await?;
+        self.spent.lock().extend(spent);
+        self.store
+            .set(store)
+            .map_err(|_| hal9_core::Error::Config("Privacy budget store is already attached".to_string()))
+    }
+
+    /// Epsilon of a query that names none
+    pub fn default_epsilon(&self) -> f64 {
+        self.config.default_epsilon
+    }
+
+    /// Epsilon `caller` has spent
+    pub fn spent(&self, caller: &str) -> f64 {
+        self.spent.lock().get(caller).copied().unwrap_or(0.0)
+    }
+
+    /// Charge `epsilon` to `caller` and release `report` with noise. Nothing
+    /// is charged when the query is refused.
+    pub async fn release(&self, caller: &str, epsilon: f64, report: &impl Serialize) -> ServerResult<PrivateReport> {
+        let report = self.prepare(epsilon, report)?;
+        let epsilon_spent = self.charge(caller, epsilon).await?;
+        Ok(self.noise(report, epsilon, epsilon_spent, &mut rand::thread_rng()))
+    }
+
+    /// [`ReportPrivacy::release`] with noise drawn from `rng`
+    pub async fn release_with<R: Rng + ?Sized>(
+        &self,
+        caller: &str,
+        epsilon: f64,
+        report: &impl Serialize,
+        rng: &mut R,
+    ) -> ServerResult<PrivateReport> {
+        let report = self.prepare(epsilon, report)?;
+        let epsilon_spent = self.charge(caller, epsilon).await?;
+        Ok(self.noise(report, epsilon, epsilon_spent, rng))
+    }
+
+    /// Check `epsilon` and turn `report` into the fields to noise
+    fn prepare(&self, epsilon: f64, report: &impl Serialize) -> ServerResult<Map<String, Value>> {
+        if !epsilon.is_finite() || epsilon <= 0.0 {
+            return Err(ServerError::InvalidInput(format!("dp_epsilon must be a positive number, got {}", epsilon)));
+        }
+        match serde_json::to_value(report)? {
+            Value::Object(fields) => Ok(fields),
+            _ => Err(ServerError::Internal("Report is not a JSON object".to_string())),
+        }
+    }
+
+    fn noise<R: Rng + ?Sized>(
+        &self,
+        mut report: Map<String, Value>,
+        epsilon: f64,
+        epsilon_spent: f64,
+        rng: &mut R,
+    ) -> PrivateReport {
+        let mut withheld = Vec::new();
+        withhold(&mut report, &mut withheld);
+        withheld.sort_unstable();
+        withheld.dedup();
+
+        let statistics = count_statistics(&report);
+        let epsilon_per_statistic = epsilon / statistics.max(1) as f64;
+        let noise = [
+            (StatKind::Count, 1.0),
+            (StatKind::Tokens, self.config.max_tokens_per_chain as f64),
+            (StatKind::CostUsd, self.config.max_cost_per_chain),
+        ]
+        .into_iter()
+        .map(|(kind, sensitivity)| (kind.name(), NoiseScale { sensitivity, scale: sensitivity / epsilon_per_statistic }))
+        .collect::<BTreeMap<_, _>>();
+
+        let mut noiser = Noiser { noise: &noise, threshold: self.config.suppression_threshold, suppressed: 0, rng };
+        noiser.object(&mut report);
+        let suppressed = noiser.suppressed;
+
+        PrivateReport {
+            report,
+            privacy: NoiseParameters {
+                mechanism: "laplace",
+                epsilon,
+                statistics,
+                epsilon_per_statistic,
+                noise,
+                suppression_threshold: self.config.suppression_threshold,
+                suppressed,
+                withheld,
+                epsilon_spent,
+                epsilon_remaining: (self.config.max_epsilon - epsilon_spent).max(0.0),
+            },
+        }
+    }
+
+    /// Add `epsilon` to what `caller` has spent, unless that would pass the
+    /// cap, returning the new total. A charge the store fails to record is
+    /// taken back and the query refused.
+    async fn charge(&self, caller: &str, epsilon: f64) -> ServerResult<f64> {
+        let total = {
+            let mut spent = self.spent.lock();
+            let total = spent.get(caller).copied().unwrap_or(0.0) + epsilon;
+            // Allow for rounding when queries add up to the cap exactly
+            if total > self.config.max_epsilon + 1e-9 {
+                return Err(ServerError::LimitExceeded(format!(
+                    "Privacy budget exhausted: {:.3} of {:.3} epsilon spent, query needs {:.3}",
+                    total - epsilon,
+                    self.config.max_epsilon,
+                    epsilon
+                )));
+            }
+            spent.insert(caller.to_string(), total);
+            total
+        };
+        if let Some(store) = self.store.get() {
+            if let Err(e) = store.add(caller, epsilon).await {
+                if let Some(spent) = self.spent.lock().get_mut(caller) {
+                    *spent -= epsilon;
+                }
+                return Err(ServerError::Internal(format!("Failed to record spent epsilon: {}", e)));
+            }
+        }
+        Ok(total)
+    }
+}
+
+/// Remove [`WITHHELD_FIELDS`] at any depth, recording which were present
+fn withhold(fields: &mut Map<String, Value>, withheld: &mut Vec<&'static str>) {
+    for field in WITHHELD_FIELDS {
+        if fields.remove(*field).is_some() {
+            withheld.push(*field);
+        }
+    }
+    for value in fields.values_mut() {
+        match value {
+            Value::Object(fields) => withhold(fields, withheld),
+            Value::Array(items) => items.iter_mut().for_each(|item| {
+                if let Value::Object(fields) = item {
+                    withhold(fields, withheld);
+                }
+            }),
+            _ => {}
+        }
+    }
+}
+
+/// Counts and sums in `fields` at any depth
+fn count_statistics(fields: &Map<String, Value>) -> usize {
+    fields.iter()
+        .map(|(name, value)| match value {
+            Value::Number(_) => usize::from(StatKind::of(name).is_some()),
+            Value::Object(fields) => count_statistics(fields),
+            Value::Array(items) => items.iter()
+                .map(|item| match item {
+                    Value::Object(fields) => count_statistics(fields),
+                    _ => 0,
+                })
+                .sum(),
+            _ => 0,
+        })
+        .sum()
+}
+
+struct Noiser<'a, R: ?Sized> {
+    noise: &'a BTreeMap<&'static str, NoiseScale>,
+    threshold: u64,
+    suppressed: usize,
+    rng: &'a mut R,
+}
+
+impl<R: Rng + ?Sized> Noiser<'_, R> {
+    /// Noise the statistics of an object, returning whether a count of its
+    /// own, outside any list, was suppressed
+    fn object(&mut self, fields: &mut Map<String, Value>) -> bool {
+        let mut suppressed = false;
+        for (name, value) in fields.iter_mut() {
+            match value {
+                Value::Number(number) => {
+                    let Some(kind) = StatKind::of(name) else { continue };
+                    let true_value = number.as_f64().unwrap_or(0.0);
+                    let noised = true_value + laplace(self.rng, self.noise[kind.name()].scale);
+                    *value = match kind {
+                        StatKind::Count => {
+                            let count = noised.round().max(0.0) as u64;
+                            if count < self.threshold {
+                                self.suppressed += 1;
+                                suppressed = true;
+                                Value::Null
+                            } else {
+                                Value::from(count)
+                            }
+                        }
+                        StatKind::Tokens => Value::from(noised.round().max(0.0) as u64),
+                        StatKind::CostUsd => Value::from(noised.max(0.0)),
+                    };
+                }
+                Value::Object(inner) => suppressed |= self.object(inner),
+                Value::Array(items) => self.list(items),
+                _ => {}
+            }
+        }
+        suppressed
+    }
+
+    /// Noise every row of a list, leaving out rows with a suppressed count
+    fn list(&mut self, items: &mut Vec<Value>) {
+        items.retain_mut(|item| match item {
+            Value::Object(fields) => !self.object(fields),
+            _ => true,
+        });
+    }
+}
@@ -0,0 +1,416 @@
+//! Retention of signal payloads, chain summaries, cost records and dead
+//! letters
+//!
+//! Each record class ages out on its own schedule, set under `retention`:
+//!
+//! - payloads: finished chains, with every step's output, and rows of the
+//!   signal log are archived after `payload_days`, then deleted
+//! - chain summaries: an archived chain's [`ChainResult`] stays in memory
+//!   for `chain_summary_days`; older chains are looked up in the archive
+//! - cost records: cost rows of the signal log are deleted after
+//!   `cost_record_days`
+//! - dead letters: dropped after `dead_letter_days`
+//! - state snapshots of `time_travel`: archived with payloads after
+//!   `payload_days`, so a past instant is rebuilt from archived snapshots
+//!   and chains alike
+//!
+//! Archives are zstd-compressed JSON lines under `archive_dir`, one file per
+//! janitor run and record class. A file only takes its final name once it
+//! is complete and synced, and nothing is deleted before that. Chains and
+//! signals a dead letter still refers to keep their payloads until the
+//! letter expires, so a dead letter never points at an archived chain.
+//!
+//! Archived chains still resolve through the chain result, graph, compare
+//! and receipt endpoints, the result flagged `archived`; reading one back
+//! from the archive files is slower than from memory. Deleting rows leaves
+//! SQLite files their size until they are compacted, see
+//! [`RetentionJanitor::compact`].
+
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use parking_lot::{Mutex, RwLock};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use tracing::{debug, error, info};
+use uuid::Uuid;
+
+use hal9_core::{
+    config::RetentionConfig,
+    sqlite::{CompactReport, SqlitePools},
+};
+
+use crate::{
+    chain_tracker::{summarize, ChainRecord, ChainResult, ChainTracker},
+    concurrency::DeadLetterQueue,
+    database_runtime::{LoggedSignal, RuntimeDatabase},
+    error::{ServerError, ServerResult},
+    time_travel::TimeTravel,
+};
+
+/// Archive file kind of finished chains
+pub const CHAINS: &str = "chains";
+
+/// Archive file kind of signal log rows
+pub const SIGNALS: &str = "signals";
+
+/// Archive file kind of state snapshots
+pub const SNAPSHOTS: &str = "snapshots";
+
+/// Name the signal log is compacted under
+pub const SIGNAL_LOG: &str = "signal_log";
+
+/// Signal log rows archived per file
+pub const SIGNAL_BATCH: i64 = 1_000;
+
+const ZSTD_LEVEL: i32 = 3;
+
+/// Files of zstd-compressed JSON lines under one directory
+#[derive(Debug, Clone)]
+pub struct Archive {
+    dir: PathBuf,
+}
+
+impl Archive {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Write `records` to a new file of `kind`, returning its path
+    pub fn write<T: Serialize>(&self, kind: &str, records: &[T], now: DateTime<Utc>) -> ServerResult<PathBuf> {
+        fs::create_dir_all(&self.dir)?;
+        let name = format!(
+            "{}-{}-{}.jsonl.zst",
+            kind,
+            now.format("%Y%m%dT%H%M%SZ"),
+            &Uuid::new_v4().simple().to_string()[..8]
+        );
+        let path = self.dir.join(name);
+        let partial = path.with_extension("zst.partial");
+
+        let mut encoder = zstd::stream::write::Encoder::new(File::create(&partial)?, ZSTD_LEVEL)?;
+        for record in records {
+            serde_json::to_writer(&mut encoder, record)?;
+            encoder.write_all(b"\n")?;
+        }
+        encoder.finish()?.sync_all()?;
+        fs::rename(&partial, &path)?;
+        Ok(path)
+    }
+
+    /// Every record of one file
+    pub fn read<T: DeserializeOwned>(path: &Path) -> ServerResult<Vec<T>> {
+        let reader = BufReader::new(zstd::stream::read::Decoder::new(File::open(path)?)?);
+        let mut records = Vec::new();
+        for line in reader.lines() {
+            records.push(serde_json::from_str(&line?)?);
+        }
+        Ok(records)
+    }
+
+    /// Complete files of `kind`, newest first
+    pub fn files(&self, kind: &str) -> ServerResult<Vec<PathBuf>> {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+        let prefix = format!("{}-", kind);
+        let mut files = Vec::new();
+        for entry in entries {
+            let path = entry?.path();
+            let name = path.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+            if name.starts_with(&prefix) && name.ends_with(".jsonl.zst") {
+                files.push(path);
+            }
+        }
+        // Names start with the time they were written
+        files.sort_by(|a, b| b.cmp(a));
+        Ok(files)
+    }
+
+    /// Complete files of `kind` written at or after `since`, newest first
+    pub fn files_since(&self, kind: &str, since: DateTime<Utc>) -> ServerResult<Vec<PathBuf>> {
+        let oldest = format!("{}-{}", kind, since.format("%Y%m%dT%H%M%SZ"));
+        let mut files = self.files(kind)?;
+        files.retain(|path| path.file_name().and_then(|n| n.to_str()).is_some_and(|name| name >= oldest.as_str()));
+        Ok(files)
+    }
+
+    /// Archived record of a chain, looking in `hint` first when the file is
+    /// known, then in every chain file
+    pub fn find_chain(&self, chain_id: &str, hint: Option<&Path>) -> ServerResult<Option<ChainRecord>> {
+        let files = hint.map(Path::to_path_buf).into_iter().chain(self.files(CHAINS)?);
+        for path in files {
+            if !path.exists() {
+                continue;
+            }
+            let reader = BufReader::new(zstd::stream::read::Decoder::new(File::open(&path)?)?);
+            for line in reader.lines() {
+                let record: ChainRecord = serde_json::from_str(&line?)?;
+                if record.chain_id == chain_id {
+                    return Ok(Some(record));
+                }
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// Summary of a chain whose payloads were archived
+#[derive(Debug, Clone)]
+struct ArchivedChain {
+    summary: ChainResult,
+    file: PathBuf,
+}
+
+impl ArchivedChain {
+    fn finished_at(&self) -> DateTime<Utc> {
+        self.summary.completed_at.unwrap_or(self.summary.created_at)
+    }
+}
+
+/// What one janitor run did
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RetentionReport {
+    pub ran_at: DateTime<Utc>,
+    pub dead_letters_dropped: usize,
+    pub chains_archived: usize,
+    /// Chains past payload retention kept because a dead letter refers to
+    /// one of their signals
+    pub chains_held_by_dead_letters: usize,
+    pub summaries_dropped: usize,
+    #[serde(default)]
+    pub snapshots_archived: usize,
+    pub signals_archived: u64,
+    pub cost_records_deleted: u64,
+    /// Archive files written
+    pub archive_files: Vec<String>,
+}
+
+impl RetentionReport {
+    fn is_empty(&self) -> bool {
+        self.dead_letters_dropped == 0
+            && self.chains_archived == 0
+            && self.summaries_dropped == 0
+            && self.snapshots_archived == 0
+            && self.signals_archived == 0
+            && self.cost_records_deleted == 0
+    }
+}
+
+/// Retention settings, what is archived and the last run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetentionStatus {
+    #[serde(flatten)]
+    pub config: RetentionConfig,
+    /// Archived chains whose summary is still in memory
+    pub archived_summaries: usize,
+    pub last_run: Option<RetentionReport>,
+}
+
+/// Archives and deletes records past their retention
+pub struct RetentionJanitor {
+    config: RetentionConfig,
+    archive: Archive,
+    chain_tracker: Arc<ChainTracker>,
+    dead_letters: Arc<DeadLetterQueue>,
+    signal_log: RwLock<Option<Arc<RuntimeDatabase>>>,
+    time_travel: RwLock<Option<Arc<TimeTravel>>>,
+    /// SQLite databases compacted on request, by name
+    databases: Mutex<Vec<(String, SqlitePools)>>,
+    summaries: DashMap<String, ArchivedChain>,
+    last_run: Mutex<Option<RetentionReport>>,
+    /// One run at a time
+    running: tokio::sync::Mutex<()>,
+}
+
+impl RetentionJanitor {
+    pub fn new(config: RetentionConfig, chain_tracker: Arc<ChainTracker>, dead_letters: Arc<DeadLetterQueue>) -> Self {
+        Self {
+            archive: Archive::new(&config.archive_dir),
+            config,
+            chain_tracker,
+            dead_letters,
+            signal_log: RwLock::new(None),
+            time_travel: RwLock::new(None),
+            databases: Mutex::new(Vec::new()),
+            summaries: DashMap::new(),
+            last_run: Mutex::new(None),
+            running: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    /// Prune the signal log's signals and cost records
+    pub fn set_signal_log(&self, signal_log: Arc<RuntimeDatabase>) {
+        *self.signal_log.write() = Some(signal_log);
+    }
+
+    /// Archive state snapshots with the payloads they were taken among
+    pub fn set_time_travel(&self, time_travel: Arc<TimeTravel>) {
+        *self.time_travel.write() = Some(time_travel);
+    }
+
+    /// Compact the SQLite database `pools` under `name` on request
+    pub fn register_database(&self, name: &str, pools: &SqlitePools) {
+        self.databases.lock().push((name.to_string(), pools.clone()));
+    }
+
+    /// Archive and delete whatever is past its retention at `now`
+    pub async fn run(&self, now: DateTime<Utc>) -> ServerResult<RetentionReport> {
+        let _running = self.running.lock().await;
+        let days_ago = |days: u32| now - chrono::Duration::days(days as i64);
+        let payload_cutoff = days_ago(self.config.payload_days);
+        let mut report = RetentionReport {
+            ran_at: now,
+            ..Default::default()
+        };
+
+        report.dead_letters_dropped = self.dead_letters.drop_before(days_ago(self.config.dead_letter_days));
+        let letters = self.dead_letters.list();
+        let held_chains: HashSet<&str> = letters.iter().filter_map(|l| ChainTracker::chain_id_of(&l.signal)).collect();
+        let held_signals: HashSet<String> = letters.iter().map(|l| l.signal.signal_id.to_string()).collect();
+
+        let (held, expired): (Vec<_>, Vec<_>) = self
+            .chain_tracker
+            .finished_before(payload_cutoff)
+            .into_iter()
+            .partition(|record| held_chains.contains(record.chain_id.as_str()));
+        report.chains_held_by_dead_letters = held.len();
+        if !expired.is_empty() {
+            let (path, expired) = self.write_archive(CHAINS, expired, now).await?;
+            for record in &expired {
+                let mut summary = summarize(record);
+                summary.archived = true;
+                self.summaries.insert(record.chain_id.clone(), ArchivedChain { summary, file: path.clone() });
+                self.chain_tracker.discard(&record.chain_id);
+            }
+            report.chains_archived = expired.len();
+            report.archive_files.push(path.display().to_string());
+        }
+
+        let summary_cutoff = days_ago(self.config.chain_summary_days);
+        let summaries = self.summaries.len();
+        self.summaries.retain(|_, chain| chain.finished_at() >= summary_cutoff);
+        report.summaries_dropped = summaries - self.summaries.len();
+
+        let time_travel = self.time_travel.read().clone();
+        if let Some(time_travel) = time_travel {
+            let expired = time_travel.snapshots_before(payload_cutoff);
+            if !expired.is_empty() {
+                let (path, expired) = self.write_archive(SNAPSHOTS, expired, now).await?;
+                time_travel.drop_before(payload_cutoff);
+                report.snapshots_archived = expired.len();
+                report.archive_files.push(path.display().to_string());
+            }
+        }
+
+        let signal_log = self.signal_log.read().clone();
+        if let Some(signal_log) = signal_log {
+            // Rows kept for dead letters stay at the front of each page
+            let mut kept = 0;
+            loop {
+                let page = signal_log.signals_before(payload_cutoff, SIGNAL_BATCH, kept).await?;
+                let full = page.len() as i64 == SIGNAL_BATCH;
+                let (held, expired): (Vec<LoggedSignal>, Vec<LoggedSignal>) =
+                    page.into_iter().partition(|signal| held_signals.contains(&signal.id));
+                kept += held.len() as i64;
+                if !expired.is_empty() {
+                    let (path, expired) = self.write_archive(SIGNALS, expired, now).await?;
+                    let ids: Vec<String> = expired.into_iter().map(|signal| signal.id).collect();
+                    report.signals_archived += signal_log.delete_signals(&ids).await?;
+                    report.archive_files.push(path.display().to_string());
+                }
+                if !full {
+                    break;
+                }
+            }
+            report.cost_records_deleted = signal_log
+                .delete_cost_records_before(days_ago(self.config.cost_record_days))
+                .await?;
+        }
+
+        *self.last_run.lock() = Some(report.clone());
+        Ok(report)
+    }
+
+    /// Write `records` to the archive off the async runtime, handing them
+    /// back with the file's path
+    async fn write_archive<T>(&self, kind: &'static str, records: Vec<T>, now: DateTime<Utc>) -> ServerResult<(PathBuf, Vec<T>)>
+    where
+        T: Serialize + Send + 'static,
+    {
+        let archive = self.archive.clone();
+        tokio::task::spawn_blocking(move || archive.write(kind, &records, now).map(|path| (path, records)))
+            .await
+            .map_err(|e| ServerError::Internal(format!("Archive writer panicked: {}", e)))?
+    }
+
+    /// Summary of an archived chain, from memory while it is kept, then from
+    /// the archive files
+    pub async fn archived_result(&self, chain_id: &str) -> ServerResult<Option<ChainResult>> {
+        if let Some(chain) = self.summaries.get(chain_id) {
+            return Ok(Some(chain.summary.clone()));
+        }
+        Ok(self.archived_record(chain_id).await?.map(|record| {
+            let mut summary = summarize(&record);
+            summary.archived = true;
+            summary
+        }))
+    }
+
+    /// Full record of an archived chain, read back from the archive files
+    pub async fn archived_record(&self, chain_id: &str) -> ServerResult<Option<ChainRecord>> {
+        let hint = self.summaries.get(chain_id).map(|chain| chain.file.clone());
+        let archive = self.archive.clone();
+        let chain_id = chain_id.to_string();
+        tokio::task::spawn_blocking(move || archive.find_chain(&chain_id, hint.as_deref()))
+            .await
+            .map_err(|e| ServerError::Internal(format!("Archive reader panicked: {}", e)))?
+    }
+
+    /// Run VACUUM and ANALYZE on every registered SQLite database and the
+    /// signal log, one at a time. Meant for after large deletions; each
+    /// database takes no writes while it is compacted.
+    pub async fn compact(&self) -> ServerResult<Vec<CompactReport>> {
+        let databases = self.databases.lock().clone();
+        let mut reports = Vec::new();
+        for (name, pools) in databases {
+            reports.push(pools.compact(&name).await.map_err(|e| ServerError::Internal(e.to_string()))?);
+        }
+        let signal_log = self.signal_log.read().clone();
+        if let Some(signal_log) = signal_log {
+            reports.push(signal_log.compact(SIGNAL_LOG).await?);
+        }
+        Ok(reports)
+    }
+
+    pub fn status(&self) -> RetentionStatus {
+        RetentionStatus {
+            config: self.config.clone(),
+            archived_summaries: self.summaries.len(),
+            last_run: self.last_run.lock().clone(),
+        }
+    }
+}
+
+/// Run the janitor every `retention.interval_secs`
+pub async fn janitor_task(janitor: Arc<RetentionJanitor>) {
+    let mut interval = tokio::time::interval(Duration::from_secs(janitor.config.interval_secs.max(1)));
+    loop {
+        interval.tick().await;
+        match janitor.run(Utc::now()).await {
+            Ok(report) if report.is_empty() => debug!("Retention janitor found nothing to archive"),
+            Ok(report) => info!(
+                "Retention janitor archived {} chains and {} signals, deleted {} cost records and {} dead letters",
+                report.chains_archived, report.signals_archived, report.cost_records_deleted, report.dead_letters_dropped
+            ),
+            Err(e) => error!("Retention janitor failed: {}", e),
+        }
+    }
+}
@@ -6,7 +6,7 @@ use tokio::sync::{mpsc, RwLock};
 use tracing::{debug, error, info, warn};
 use dashmap::DashMap;
 
-use hal9_core::{Result, Error, NeuronSignal};
+use hal9_core::{metadata_schema::keys, Result, Error, NeuronSignal};
 use crate::{
     router::local::SignalRouter,
     network::{
@@ -194,7 +194,7 @@ impl DistributedRouter {
                 debug!("Received signal from {}: {:?}", from_server, signal.signal_id);
                 
                 // Check hop count
-                let hop_count = signal.metadata.get("hop_count")
+                let hop_count = signal.metadata.get(keys::network::HOP_COUNT)
                     .and_then(|v| v.parse::<u32>().ok())
                     .unwrap_or(0);
                     
@@ -205,8 +205,8 @@ impl DistributedRouter {
                 
                 // Add server trace
                 let mut signal = signal;
-                signal.metadata.insert("from_server".to_string(), from_server);
-                signal.metadata.insert("hop_count".to_string(), (hop_count + 1).to_string());
+                signal.metadata.insert(keys::network::FROM_SERVER.to_string(), from_server);
+                signal.metadata.insert(keys::network::HOP_COUNT.to_string(), (hop_count + 1).to_string());
                 
                 // Route locally
                 if let Err(e) = local_router.send_signal(signal).await {
@@ -233,7 +233,7 @@ impl DistributedRouter {
             
             // Add metadata
             let mut signal = signal;
-            signal.metadata.insert("via_server".to_string(), self.server_id.clone());
+            signal.metadata.insert(keys::network::VIA_SERVER.to_string(), self.server_id.clone());
             
             // Send over network
             return self.transport.send_signal(&server_id, signal).await;
@@ -3,15 +3,36 @@
 use dashmap::DashMap;
 use std::sync::Arc;
 use tokio::sync::mpsc;
-use tracing::{debug, error, info};
+use tracing::{debug, error, info, Instrument};
+use uuid::Uuid;
 
-use hal9_core::{Error, Result, NeuronSignal, NeuronConfig, NeuronInterface};
+use hal9_core::{Error, Result, NeuronSignal, NeuronConfig, NeuronInterface, PropagationType};
+use hal9_core::hierarchical::intelligence::{SignalFlowEvent, SignalFlowHistory};
+use crate::approvals::ApprovalGates;
+use crate::chain_tracker::{mark_gradient, ChainTracker, PARENT_ID_KEY, USER_ID_KEY};
+use crate::concurrency::{Admission, OverflowMode};
+use crate::degraded::{self, DegradedAdmission, DegradedMode, DEFERRED_REASON, HEURISTIC_MODEL};
+use crate::fair_scheduler::FairScheduler;
+use crate::fan_out;
+use crate::layer_pause::{LayerAdmission, LayerGate};
+use crate::logging::signal_span;
+use crate::model_registry::ModelResolution;
 use crate::neuron::NeuronRegistry;
-use crate::performance::{SignalBuffer, ParallelExecutor};
+use crate::performance::{SignalBuffer, ParallelExecutor, PollMonitor};
+use crate::recovery::{RecoveryContext, RecoveryOutcome};
+use crate::scaling::PendingSignals;
+use crate::self_organizer::LoadTracker;
+use crate::speculation::{Settlement, Speculator, SPECULATIVE_OF_KEY};
+
+/// Metadata key naming the neuron that shed a signal; shed signals are
+/// dead-lettered rather than shed a second time
+pub const SHED_FROM_KEY: &str = hal9_core::metadata_schema::keys::routing::SHED_FROM;
 
 /// Routing table for signal delivery
 pub struct RoutingTable {
     routes: Arc<DashMap<String, Vec<String>>>,
+    /// Clones serving signals addressed to a neuron, keyed by the original's ID
+    siblings: Arc<DashMap<String, Vec<String>>>,
 }
 
 impl Default for RoutingTable {
@@ -25,6 +46,7 @@ impl RoutingTable {
     pub fn new() -> Self {
         Self {
             routes: Arc::new(DashMap::new()),
+            siblings: Arc::new(DashMap::new()),
         }
     }
     
@@ -41,6 +63,16 @@ impl RoutingTable {
         info!("Built routing table with {} entries", self.routes.len());
     }
     
+    /// Replace a neuron's forward routes
+    pub fn set_routes(&self, neuron_id: &str, forwards: Vec<String>) {
+        self.routes.insert(neuron_id.to_string(), forwards);
+    }
+    
+    /// Forget a removed neuron's forward routes
+    pub fn remove_routes(&self, neuron_id: &str) {
+        self.routes.remove(neuron_id);
+    }
+    
     /// Get forward connections for a neuron
     pub fn get_forwards(&self, neuron_id: &str) -> Vec<String> {
         self.routes.get(neuron_id)
@@ -54,6 +86,62 @@ impl RoutingTable {
             .map(|entry| entry.contains(&to.to_string()))
             .unwrap_or(false)
     }
+    
+    /// Share a neuron's traffic with a clone, which inherits its forward routes
+    pub fn add_sibling(&self, neuron_id: &str, clone_id: &str) {
+        let forwards = self.get_forwards(neuron_id);
+        self.routes.insert(clone_id.to_string(), forwards);
+        self.siblings.entry(neuron_id.to_string())
+            .or_default()
+            .push(clone_id.to_string());
+    }
+    
+    /// Stop sending a neuron's traffic to a clone
+    pub fn remove_sibling(&self, neuron_id: &str, clone_id: &str) {
+        if let Some(mut clones) = self.siblings.get_mut(neuron_id) {
+            clones.retain(|id| id != clone_id);
+        }
+        self.siblings.remove_if(neuron_id, |_, clones| clones.is_empty());
+        self.routes.remove(clone_id);
+    }
+    
+    /// The neuron and its clones
+    pub fn siblings(&self, neuron_id: &str) -> Vec<String> {
+        let mut ids = vec![neuron_id.to_string()];
+        if let Some(clones) = self.siblings.get(neuron_id) {
+            ids.extend(clones.iter().cloned());
+        }
+        ids
+    }
+    
+    /// Pick the sibling with the lowest load to receive a signal for `neuron_id`
+    pub fn resolve(&self, neuron_id: &str, load: impl Fn(&str) -> usize) -> String {
+        match self.siblings.get(neuron_id) {
+            Some(clones) => std::iter::once(neuron_id)
+                .chain(clones.iter().map(String::as_str))
+                .min_by_key(|id| load(id))
+                .unwrap_or(neuron_id)
+                .to_string(),
+            None => neuron_id.to_string(),
+        }
+    }
+}
+
+/// What processing a signal needs from the router, shared by its tasks
+#[derive(Clone)]
+struct SignalContext {
+    registry: Arc<NeuronRegistry>,
+    routing_table: Arc<RoutingTable>,
+    signal_tx: mpsc::Sender<NeuronSignal>,
+    chain_tracker: Arc<ChainTracker>,
+    scheduler: Option<Arc<FairScheduler>>,
+    load_tracker: Option<Arc<LoadTracker>>,
+    signal_flow: Option<Arc<SignalFlowHistory>>,
+    pending: Option<Arc<PendingSignals>>,
+    speculation: Option<Arc<Speculator>>,
+    degraded: Option<Arc<DegradedMode>>,
+    approvals: Option<Arc<ApprovalGates>>,
+    poll_monitor: Option<Arc<PollMonitor>>,
 }
 
 /// Signal router for processing and distributing signals
@@ -66,6 +154,16 @@ pub struct SignalRouter {
     shutdown_tx: Option<mpsc::Sender<()>>,
     signal_buffer: Arc<SignalBuffer<NeuronSignal>>,
     parallel_executor: Arc<ParallelExecutor>,
+    chain_tracker: Arc<ChainTracker>,
+    scheduler: Option<Arc<FairScheduler>>,
+    load_tracker: Option<Arc<LoadTracker>>,
+    signal_flow: Option<Arc<SignalFlowHistory>>,
+    layer_gate: Option<Arc<LayerGate>>,
+    pending: Option<Arc<PendingSignals>>,
+    speculation: Option<Arc<Speculator>>,
+    degraded: Option<Arc<DegradedMode>>,
+    approvals: Option<Arc<ApprovalGates>>,
+    poll_monitor: Option<Arc<PollMonitor>>,
 }
 
 impl SignalRouter {
@@ -84,9 +182,72 @@ impl SignalRouter {
                 std::time::Duration::from_millis(50) // flush every 50ms
             )),
             parallel_executor: Arc::new(ParallelExecutor::new(8)), // 8 parallel workers
+            chain_tracker: Arc::new(ChainTracker::new()),
+            scheduler: None,
+            load_tracker: None,
+            signal_flow: None,
+            layer_gate: None,
+            pending: None,
+            speculation: None,
+            degraded: None,
+            approvals: None,
+            poll_monitor: None,
         }
     }
     
+    /// Set the scheduler that fairly shares processing slots between users
+    pub fn set_scheduler(&mut self, scheduler: Arc<FairScheduler>) {
+        self.scheduler = Some(scheduler);
+    }
+    
+    /// Set the tracker that bounds per-neuron concurrency and records load;
+    /// signals are then balanced across a neuron's clones
+    pub fn set_load_tracker(&mut self, load_tracker: Arc<LoadTracker>) {
+        self.load_tracker = Some(load_tracker);
+    }
+    
+    /// Set the history that records which signal produced which, for
+    /// emergence detection
+    pub fn set_signal_flow(&mut self, signal_flow: Arc<SignalFlowHistory>) {
+        self.signal_flow = Some(signal_flow);
+    }
+    
+    /// Set the gate that holds signals for paused layers
+    pub fn set_layer_gate(&mut self, layer_gate: Arc<LayerGate>) {
+        self.layer_gate = Some(layer_gate);
+    }
+    
+    /// Set the tracker of signals waiting to be processed, for autoscaling
+    pub fn set_pending_signals(&mut self, pending: Arc<PendingSignals>) {
+        self.pending = Some(pending);
+    }
+    
+    /// Set the speculation that starts children from streamed partial output
+    pub fn set_speculation(&mut self, speculation: Arc<Speculator>) {
+        self.speculation = Some(speculation);
+    }
+    
+    /// Set the degraded mode that decomposes or parks signals while every
+    /// provider is down
+    pub fn set_degraded(&mut self, degraded: Arc<DegradedMode>) {
+        self.degraded = Some(degraded);
+    }
+    
+    /// Set the gates that hold outputs needing approval
+    pub fn set_approvals(&mut self, approvals: Arc<ApprovalGates>) {
+        self.approvals = Some(approvals);
+    }
+    
+    /// Set the monitor signal tasks are spawned through while profiling
+    pub fn set_poll_monitor(&mut self, poll_monitor: Arc<PollMonitor>) {
+        self.poll_monitor = Some(poll_monitor);
+    }
+    
+    /// Set the chain tracker that records neuron outputs per chain
+    pub fn set_chain_tracker(&mut self, chain_tracker: Arc<ChainTracker>) {
+        self.chain_tracker = chain_tracker;
+    }
+    
     /// Start the signal processing loop
     pub async fn start(&mut self) -> Result<()> {
         let mut signal_rx = self.signal_rx.take()
@@ -95,10 +256,31 @@ impl SignalRouter {
         let (shutdown_tx, mut shutdown_rx) = mpsc::channel(1);
         self.shutdown_tx = Some(shutdown_tx);
         
-        let registry = self.registry.clone();
-        let routing_table = self.routing_table.clone();
-        let signal_tx = self.signal_tx.clone();
+        let ctx = SignalContext {
+            registry: self.registry.clone(),
+            routing_table: self.routing_table.clone(),
+            signal_tx: self.signal_tx.clone(),
+            chain_tracker: self.chain_tracker.clone(),
+            scheduler: self.scheduler.clone(),
+            load_tracker: self.load_tracker.clone(),
+            signal_flow: self.signal_flow.clone(),
+            pending: self.pending.clone(),
+            speculation: self.speculation.clone(),
+            degraded: self.degraded.clone(),
+            approvals: self.approvals.clone(),
+            poll_monitor: self.poll_monitor.clone(),
+        };
         let signal_buffer = self.signal_buffer.clone();
+        let layer_gate = self.layer_gate.clone();
+        if let Some(gate) = &layer_gate {
+            gate.attach(ctx.signal_tx.clone());
+        }
+        if let Some(degraded) = &ctx.degraded {
+            degraded.attach(ctx.signal_tx.clone());
+        }
+        if let Some(approvals) = &ctx.approvals {
+            approvals.attach(ctx.signal_tx.clone());
+        }
         
         info!("Starting signal router");
         
@@ -112,21 +294,39 @@ impl SignalRouter {
                     Some(signal) = signal_rx.recv() => {
                         debug!("Processing signal: {} -> {}", signal.from_neuron, signal.to_neuron);
                         
-                        // Buffer signals for batch processing
-                        let signal_buffer = signal_buffer.clone();
-                        let registry_clone = registry.clone();
-                        let routing_table_clone = routing_table.clone();
-                        let signal_tx_clone = signal_tx.clone();
+                        // Signals for paused layers wait in the gate until resumed
+                        let signal = match &layer_gate {
+                            Some(gate) => {
+                                // Room reserved for a signal that is held goes
+                                // back; it is sent again on resume
+                                let (target, signal_id) = (signal.to_neuron.clone(), signal.signal_id);
+                                match gate.admit(signal) {
+                                    LayerAdmission::Deliver(signal) => signal,
+                                    LayerAdmission::Held => {
+                                        release_room(&ctx.registry, &target, &signal_id);
+                                        continue;
+                                    }
+                                    LayerAdmission::Overflow(signal) => {
+                                        release_room(&ctx.registry, &target, &signal_id);
+                                        let err = format!("Layer {} is paused and its hold queue is full", signal.layer_to);
+                                        ctx.chain_tracker.record_step(&signal, Err(&err), 0);
+                                        ctx.registry.dead_letters().push(&target, "layer_paused", signal);
+                                        continue;
+                                    }
+                                }
+                            }
+                            None => signal,
+                        };
+                        if let Some(pending) = &ctx.pending {
+                            pending.enter(signal.signal_id);
+                        }
                         
+                        // Buffer signals for batch processing
                         if let Some(batch) = signal_buffer.add(signal) {
                             // Process batch in parallel
+                            let ctx = ctx.clone();
                             tokio::spawn(async move {
-                                Self::process_signal_batch(
-                                    &registry_clone,
-                                    &routing_table_clone,
-                                    &signal_tx_clone,
-                                    batch
-                                ).await;
+                                Self::process_signal_batch(&ctx, batch).await;
                             });
                         }
                     }
@@ -135,17 +335,9 @@ impl SignalRouter {
                         let buffered = signal_buffer.flush();
                         if !buffered.is_empty() {
                             debug!("Flushing {} buffered signals", buffered.len());
-                            let registry_clone = registry.clone();
-                            let routing_table_clone = routing_table.clone();
-                            let signal_tx_clone = signal_tx.clone();
-                            
+                            let ctx = ctx.clone();
                             tokio::spawn(async move {
-                                Self::process_signal_batch(
-                                    &registry_clone,
-                                    &routing_table_clone,
-                                    &signal_tx_clone,
-                                    buffered
-                                ).await;
+                                Self::process_signal_batch(&ctx, buffered).await;
                             });
                         }
                     }
@@ -153,12 +345,7 @@ impl SignalRouter {
                         // Flush any remaining signals
                         let remaining = signal_buffer.flush();
                         if !remaining.is_empty() {
-                            Self::process_signal_batch(
-                                &registry,
-                                &routing_table,
-                                &signal_tx,
-                                remaining
-                            ).await;
+                            Self::process_signal_batch(&ctx, remaining).await;
                         }
                         info!("Signal router shutting down");
                         break;
@@ -171,31 +358,24 @@ impl SignalRouter {
     }
     
     /// Process a batch of signals in parallel
-    async fn process_signal_batch(
-        registry: &Arc<NeuronRegistry>,
-        routing_table: &Arc<RoutingTable>,
-        signal_tx: &mpsc::Sender<NeuronSignal>,
-        signals: Vec<NeuronSignal>,
-    ) {
+    async fn process_signal_batch(ctx: &SignalContext, signals: Vec<NeuronSignal>) {
         let start = std::time::Instant::now();
         debug!("Processing batch of {} signals", signals.len());
         
         // Process signals in parallel
         let tasks: Vec<_> = signals.into_iter().map(|signal| {
-            let registry = registry.clone();
-            let routing_table = routing_table.clone();
-            let signal_tx = signal_tx.clone();
+            let task_ctx = ctx.clone();
+            let span = signal_span(&signal);
             
-            tokio::spawn(async move {
-                if let Err(e) = Self::process_signal(
-                    &registry,
-                    &routing_table,
-                    &signal_tx,
-                    signal
-                ).await {
-                    error!("Failed to process signal: {}", e);
+            let task = async move {
+                if let Err(e) = Self::process_signal(&task_ctx, signal).await {
+                    error!(event = "signal_failed", "Failed to process signal: {}", e);
                 }
-            })
+            }.instrument(span);
+            match &ctx.poll_monitor {
+                Some(monitor) => monitor.spawn(task),
+                None => tokio::spawn(task),
+            }
         }).collect();
         
         // Wait for all tasks to complete
@@ -207,26 +387,313 @@ impl SignalRouter {
     }
     
     /// Process a single signal
-    async fn process_signal(
-        registry: &Arc<NeuronRegistry>,
-        _routing_table: &Arc<RoutingTable>,
-        signal_tx: &mpsc::Sender<NeuronSignal>,
-        signal: NeuronSignal,
-    ) -> Result<()> {
-        // Get target neuron
-        let neuron = registry.get(&signal.to_neuron)
-            .ok_or_else(|| Error::Routing(format!("Neuron {} not found", signal.to_neuron)))?;
+    async fn process_signal(ctx: &SignalContext, mut signal: NeuronSignal) -> Result<()> {
+        let SignalContext {
+            registry, routing_table, signal_tx, chain_tracker, scheduler, load_tracker,
+            signal_flow, pending, speculation, degraded, approvals, ..
+        } = ctx;
+        
+        // Pending until a neuron starts on it, whichever way this returns
+        let waiting = pending.as_ref().map(|pending| pending.guard(signal.signal_id));
+        
+        // Room its sender reserved for it, given back if it is not admitted
+        let reservation = registry.get(&signal.to_neuron)
+            .and_then(|neuron| neuron.concurrency().take_reservation(&signal.signal_id));
+        
+        // Chain roots enter the flow history here, other signals when produced
+        if ChainTracker::chain_id_of(&signal) == Some(signal.signal_id.to_string().as_str()) {
+            record_flow(signal_flow, &signal, None);
+        }
+        
+        // A forward addressed to a routing group goes where its sender's
+        // policy sends it
+        if signal.propagation_type == PropagationType::Forward {
+            let policy = registry.get(&signal.from_neuron).and_then(|sender| sender.routing());
+            if let Some(policy) = policy.filter(|policy| policy.routes(&signal.to_neuron)) {
+                let load = |id: &str| {
+                    registry.get(id).map_or(0, |neuron| {
+                        let stats = neuron.concurrency().stats();
+                        stats.in_flight + stats.queued
+                    })
+                };
+                let route = policy.choose(&signal.to_neuron, &signal.payload.activation.content, load).await;
+                debug!(event = "signal_routed", "Routing group {} of {} chose {}", route.group, signal.from_neuron, route.target);
+                route.apply_to(&mut signal.metadata);
+                signal.to_neuron = route.target;
+            }
+        }
+        
+        // Get target neuron, or its least loaded clone
+        let target = match load_tracker {
+            Some(load) => routing_table.resolve(&signal.to_neuron, |id| load.queue_depth(id)),
+            None => signal.to_neuron.clone(),
+        };
+        let neuron = match registry.get(&target) {
+            Some(neuron) => neuron,
+            None => {
+                let err = format!("Neuron {} not found", signal.to_neuron);
+                chain_tracker.record_step(&signal, Err(&err), 0);
+                return Err(Error::Routing(err));
+            }
+        };
+        
+        // While every provider is down, answer with templates or park the
+        // signal until they recover
+        let admission = match degraded {
+            Some(degraded) => degraded.admit(&signal, neuron.layer.as_str()),
+            None => DegradedAdmission::Process,
+        };
+        if let Some(degraded) = degraded {
+            match admission {
+                DegradedAdmission::Decompose => {
+                    let new_signals = neuron.decompose(&degraded.tasks(&signal.payload.activation.content), &signal);
+                    if let Some(truncation) = neuron.take_fan_out_truncation(&signal.signal_id) {
+                        chain_tracker.record_truncation(&signal, truncation);
+                    }
+                    chain_tracker.record_model(&signal, HEURISTIC_MODEL);
+                    chain_tracker.record_step(&signal, Ok(&degraded::describe(&new_signals)), new_signals.len());
+                    for new_signal in new_signals {
+                        record_flow(signal_flow, &new_signal, Some(&signal));
+                        if let Err(e) = signal_tx.send(new_signal).await {
+                            error!("Failed to queue signal: {}", e);
+                        }
+                    }
+                    return Ok(());
+                }
+                DegradedAdmission::Park => {
+                    park(degraded, registry, chain_tracker, signal, false);
+                    return Ok(());
+                }
+                DegradedAdmission::Process | DegradedAdmission::Probe => {}
+            }
+        }
+        
+        // Claim a place in the neuron's queue; a full queue overflows by its mode
+        let _slot = match neuron.admit_reserved(reservation).await {
+            Admission::Admitted(permit) => permit,
+            Admission::Rejected => {
+                let err = format!("Neuron {} queue is full", target);
+                chain_tracker.record_step(&signal, Err(&err), 0);
+                registry.dead_letters().push(&target, "queue_full", signal);
+                return Err(Error::ResourceExhausted(err));
+            }
+            Admission::Shed(fallback) => {
+                let fallback_layer = registry.get(&fallback).map(|n| n.layer.as_str().to_string());
+                let (Some(layer), false) = (fallback_layer, signal.metadata.contains_key(SHED_FROM_KEY)) else {
+                    let err = format!("Neuron {} queue is full and fallback {} cannot take the signal", target, fallback);
+                    chain_tracker.record_step(&signal, Err(&err), 0);
+                    registry.dead_letters().push(&target, "shed_failed", signal);
+                    return Err(Error::ResourceExhausted(err));
+                };
+                debug!(event = "signal_shed", "Neuron {} is full, shedding signal to {}", target, fallback);
+                let mut shed = signal;
+                shed.metadata.insert(SHED_FROM_KEY.to_string(), target);
+                shed.to_neuron = fallback;
+                shed.layer_to = layer;
+                return signal_tx.send(shed).await
+                    .map_err(|_| Error::Communication("Failed to queue shed signal".to_string()));
+            }
+        };
             
+        // Wait for the neuron itself; queued time counts towards its latency
+        let _load = match load_tracker {
+            Some(load) => Some(load.begin(&target).await),
+            None => None,
+        };
+        
+        // Wait for a processing slot; contended slots rotate between users
+        let _permit = match scheduler {
+            Some(scheduler) => {
+                let user = signal.metadata.get(USER_ID_KEY).map(|s| s.as_str()).unwrap_or("anonymous");
+                Some(scheduler.acquire(user).await)
+            }
+            None => None,
+        };
+        
+        // A speculative signal its parent cancelled meanwhile is dropped
+        drop(waiting);
+        let tagged = speculation.as_ref().filter(|_| signal.metadata.contains_key(SPECULATIVE_OF_KEY));
+        if let Some(speculation) = tagged {
+            if speculation.settlement(&signal.signal_id) == Settlement::Cancelled {
+                speculation.settled(&signal.signal_id).await;
+                chain_tracker.withdraw(&signal);
+                return Ok(());
+            }
+        }
+        
+        // Neurons on speculative layers stream their response, and the
+        // children of a complete forward instruction in it start early
+        let (partial, watcher) = match speculation {
+            Some(speculation)
+                if speculation.applies_to(neuron.layer.as_str())
+                    && signal.propagation_type == PropagationType::Forward
+                    && !neuron.requires_approval() =>
+            {
+                let (partial_tx, partial_rx) = mpsc::unbounded_channel();
+                let watcher = speculation.watch(
+                    neuron.clone(),
+                    signal.clone(),
+                    partial_rx,
+                    chain_tracker.clone(),
+                    signal_tx.clone(),
+                );
+                (Some(partial_tx), Some(watcher))
+            }
+            _ => (None, None),
+        };
+        
         // Process signal
-        match neuron.process_signal(&signal).await {
+        let processed = match partial {
+            Some(partial) => neuron.process_streaming(&signal, partial).await,
+            None => neuron.process_signal(&signal).await,
+        };
+        let mut recovery = None;
+        let result = match processed {
+            // A failure runs the neuron's recovery playbook, if it has one
+            Err(e) => match neuron.recovery() {
+                Some(playbook) => {
+                    let siblings = routing_table.siblings(&signal.to_neuron)
+                        .into_iter()
+                        .filter(|id| *id != target)
+                        .filter_map(|id| registry.get(&id))
+                        .collect();
+                    let error = e.to_string();
+                    let ctx = RecoveryContext {
+                        signal: &signal,
+                        neuron: &neuron,
+                        error: &error,
+                        siblings,
+                        dead_letters: registry.dead_letters(),
+                    };
+                    let report = playbook.run(&ctx).await;
+                    let result = report.response.clone().ok_or(e);
+                    recovery = Some(report);
+                    result
+                }
+                None => Err(e),
+            },
+            ok => ok,
+        };
+        
+        let speculative = match watcher {
+            Some(watcher) => watcher.await.unwrap_or_default(),
+            None => Vec::new(),
+        };
+        
+        // A speculative signal holds its output until its parent settles it
+        if let Some(speculation) = tagged {
+            if speculation.settled(&signal.signal_id).await == Settlement::Cancelled {
+                speculation.settle(speculative, &mut Vec::new());
+                if result.is_ok() {
+                    speculation.record_wasted(neuron.last_token_usage().as_ref());
+                }
+                chain_tracker.withdraw(&signal);
+                return Ok(());
+            }
+            signal.metadata.remove(SPECULATIVE_OF_KEY);
+        }
+        
+        match result {
             Ok(response) => {
-                debug!("Neuron {} processed signal successfully", neuron.id());
+                debug!(event = "signal_processed", "Neuron {} processed signal successfully", neuron.id());
                 
                 // Parse response for new signals
-                let new_signals = neuron.parse_response(&response, &signal);
+                let mut new_signals = neuron.parse_response(&response, &signal);
+                if let Some(report) = &recovery {
+                    for new_signal in &mut new_signals {
+                        report.apply_to(&mut new_signal.metadata);
+                    }
+                }
+                if let Some(usage) = neuron.last_token_usage() {
+                    chain_tracker.record_usage(&signal, usage.prompt_tokens, usage.completion_tokens);
+                    if let Some(model) = &usage.model {
+                        chain_tracker.record_model(&signal, model);
+                    }
+                    // A retired model was replaced by its remap
+                    if let (Some(model), Some(remapped_from)) = (usage.model, usage.remapped_from) {
+                        let resolution = ModelResolution { model, remapped_from: Some(remapped_from) };
+                        for new_signal in &mut new_signals {
+                            resolution.apply_to(&mut new_signal.metadata);
+                        }
+                    }
+                }
+                if let Some(report) = neuron.take_format_report(&signal.signal_id) {
+                    chain_tracker.record_format(&signal, report);
+                }
+                if let Some(cited) = neuron.take_citations(&signal.signal_id) {
+                    chain_tracker.record_citations(&signal, cited);
+                }
+                // In strict mode a response over a fan-out limit fails the chain
+                let refused = neuron.take_fan_out_truncation(&signal.signal_id).and_then(|truncation| {
+                    let failed = truncation.failed.then(|| fan_out::describe(&truncation));
+                    chain_tracker.record_truncation(&signal, truncation);
+                    failed
+                });
+                let outcome = match &refused {
+                    Some(reason) => Err(reason.as_str()),
+                    None => Ok(response.as_str()),
+                };
+                // An output needing approval is held with the signals it
+                // forwards, and recorded as a step once decided on
+                let gate = match approvals {
+                    Some(approvals) if outcome.is_ok() && signal.propagation_type == PropagationType::Forward => {
+                        neuron.approval_reason(&response).map(|reason| (approvals, reason))
+                    }
+                    _ => None,
+                };
+                // Children started early that the final output still forwards
+                // are confirmed rather than sent again; they are already
+                // counted in the chain. A held output confirms none, so its
+                // children wait at the gate like the rest.
+                let confirmed = match speculation {
+                    Some(speculation) if gate.is_some() => {
+                        speculation.settle(speculative, &mut Vec::new());
+                        Vec::new()
+                    }
+                    Some(speculation) if !speculative.is_empty() => speculation.settle(speculative, &mut new_signals),
+                    _ => Vec::new(),
+                };
+                if gate.is_none() {
+                    chain_tracker.record_step(&signal, outcome, new_signals.len());
+                }
+                // A provider answering again ends an outage's degraded mode
+                if let Some(degraded) = degraded {
+                    degraded.check_recovery(registry).await;
+                }
+                for new_signal in new_signals.iter().chain(&confirmed) {
+                    record_flow(signal_flow, new_signal, Some(&signal));
+                }
+                if let Some((approvals, reason)) = gate {
+                    debug!(event = "output_held", "Holding output of neuron {} for approval", neuron.id());
+                    approvals.hold(signal, &response, new_signals, &reason);
+                    return Ok(());
+                }
                 
-                // Queue new signals in parallel if multiple
-                if new_signals.len() > 1 {
+                // Hold this neuron's slot until blocking targets have room, so a
+                // full queue downstream slows the neurons feeding it. Room is
+                // reserved for one signal at a time, right before it is sent.
+                let blocking: Vec<_> = new_signals
+                    .iter()
+                    .map(|new_signal| {
+                        registry.get(&new_signal.to_neuron)
+                            .filter(|next| next.concurrency().config().overflow == OverflowMode::Block)
+                    })
+                    .collect();
+                if blocking.iter().any(Option::is_some) {
+                    for (new_signal, next) in new_signals.into_iter().zip(blocking) {
+                        let signal_id = new_signal.signal_id;
+                        if let Some(next) = &next {
+                            next.concurrency().reserve_room(signal_id).await;
+                        }
+                        if let Err(e) = signal_tx.send(new_signal).await {
+                            error!("Failed to queue signal: {}", e);
+                            if let Some(next) = next {
+                                next.concurrency().release_room(&signal_id);
+                            }
+                        }
+                    }
+                } else if new_signals.len() > 1 {
+                    // Queue new signals in parallel if multiple
                     let signal_tx = signal_tx.clone();
                     tokio::spawn(async move {
                         for new_signal in new_signals {
@@ -245,17 +712,46 @@ impl SignalRouter {
                 }
             }
             Err(e) => {
-                error!("Neuron failed to process signal: {}", e);
+                error!(event = "neuron_error", "Neuron failed to process signal: {}", e);
+                let timeout = neuron.take_timeout_decision(&signal.signal_id);
+                neuron.take_prompt_composition(&signal.signal_id);
+                if let Some(speculation) = speculation {
+                    speculation.settle(speculative, &mut Vec::new());
+                }
+                
+                // A failure that leaves every provider down parks the branch
+                // instead of failing it, unless recovery dead-lettered it
+                let dead_lettered = recovery.as_ref().is_some_and(|r| r.outcome == RecoveryOutcome::DeadLettered);
+                if let (Some(degraded), false) = (degraded, dead_lettered) {
+                    if degraded.check_outage(registry).await {
+                        park(degraded, registry, chain_tracker, signal, admission == DegradedAdmission::Probe);
+                        return Ok(());
+                    }
+                }
                 
                 // Generate error signal if appropriate
-                if e.is_recoverable() {
-                    let error_signal = NeuronSignal::backward(
+                let recoverable = e.is_recoverable();
+                chain_tracker.record_step(&signal, Err(&e.to_string()), usize::from(recoverable));
+                
+                if recoverable {
+                    let mut error_signal = NeuronSignal::backward(
                         &signal.to_neuron,
                         &signal.from_neuron,
                         &signal.layer_to,
                         &signal.layer_from,
                         hal9_core::Gradient::new(e.to_string(), 1.0),
                     );
+                    error_signal.metadata = signal.metadata.clone();
+                    error_signal.metadata.insert(PARENT_ID_KEY.to_string(), signal.signal_id.to_string());
+                    mark_gradient(&mut error_signal, e.class(), 1.0);
+                    fan_out::mark_feedback(&mut error_signal.metadata);
+                    if let Some(report) = &recovery {
+                        report.apply_to(&mut error_signal.metadata);
+                    }
+                    if let Some(decision) = &timeout {
+                        decision.apply_to(&mut error_signal.metadata);
+                    }
+                    record_flow(signal_flow, &error_signal, Some(&signal));
                     
                     if let Err(e) = signal_tx.send(error_signal).await {
                         error!("Failed to queue error signal: {}", e);
@@ -287,3 +783,37 @@ impl SignalRouter {
     }
 }
 
+/// Park `signal` until providers recover, or dead-letter it if too many
+/// signals are parked already
+fn park(
+    degraded: &DegradedMode,
+    registry: &NeuronRegistry,
+    chain_tracker: &ChainTracker,
+    signal: NeuronSignal,
+    probe: bool,
+) {
+    debug!(event = "signal_parked", "Providers are down, parking signal for {}", signal.to_neuron);
+    chain_tracker.record_deferral(&signal, DEFERRED_REASON);
+    if let Err(signal) = degraded.park(signal, probe) {
+        let err = "Providers are down and too many signals are parked".to_string();
+        chain_tracker.record_step(&signal, Err(&err), 0);
+        let target = signal.to_neuron.clone();
+        registry.dead_letters().push(&target, "degraded_overflow", *signal);
+    }
+}
+
+/// Give back room `target` reserved for a signal that will not reach it
+fn release_room(registry: &NeuronRegistry, target: &str, signal_id: &Uuid) {
+    if let Some(neuron) = registry.get(target) {
+        neuron.concurrency().release_room(signal_id);
+    }
+}
+
+/// Record `signal` in the flow history as produced by `parent`
+fn record_flow(signal_flow: &Option<Arc<SignalFlowHistory>>, signal: &NeuronSignal, parent: Option<&NeuronSignal>) {
+    if let Some(signal_flow) = signal_flow {
+        let batch_id = signal.batch_id.to_string();
+        let chain_id = ChainTracker::chain_id_of(signal).unwrap_or(&batch_id);
+        signal_flow.record(SignalFlowEvent::new(chain_id, signal, parent.map(|p| p.signal_id)));
+    }
+}
@@ -2,6 +2,8 @@
 
 pub mod local;
 pub mod distributed;
+pub mod policy;
 
 pub use local::{SignalRouter, RoutingTable};
-pub use distributed::{DistributedRouter, DistributedConfig, RoutingInfo};
\ No newline at end of file
+pub use distributed::{DistributedRouter, DistributedConfig, RoutingInfo};
+pub use policy::{Route, RoutingPolicy, RoutingStrategy};
\ No newline at end of file
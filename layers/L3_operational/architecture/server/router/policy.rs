@@ -0,0 +1,337 @@
+//! Routing policies for forwards that name no neuron
+//!
+//! A neuron's forward connections are a static list. A neuron with a
+//! `settings.routing` policy can also leave the choice to the router: a
+//! response whose `FORWARD_TO:` line is empty, or names the policy's
+//! `group`, produces one signal addressed to the group, and the router picks
+//! one of the policy's targets when the signal arrives:
+//!
+//! ```yaml
+//! settings:
+//!   routing:
+//!     strategy: content_match   # content_match | round_robin | least_loaded | static
+//!     group: implementers
+//!     targets: [devops-l3, general-l3]   # default: forward_connections
+//!     fallback: general-l3               # default: the first target
+//!     rules:
+//!       - name: infrastructure
+//!         target: devops-l3
+//!         keywords: [kubernetes, terraform, "load balancer"]
+//!         pattern: "(?i)\\bdeploy(ment)?s?\\b"
+//!         examples: ["Provision a cluster for the staging environment"]
+//!         min_similarity: 0.6
+//! ```
+//!
+//! - `content_match` scores every rule against the signal's content: a
+//!   keyword or pattern match scores 1, otherwise the closest example by
+//!   embedding similarity scores its similarity if it reaches
+//!   `min_similarity`. The best score wins and ties go to the rule declared
+//!   first; with no match the signal goes to `fallback`.
+//! - `round_robin` takes the targets in turn.
+//! - `least_loaded` takes the target with the fewest signals admitted, the
+//!   first declared on a tie.
+//! - `static` always takes `fallback`.
+//!
+//! The chosen route is written into the signal's metadata
+//! (`routing.group`, `routing.route`, `routing.strategy`, and `routing.rule`
+//! for content matches).
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use hal9_core::{memory::EmbeddingGenerator, metadata_schema::keys, Error, NeuronConfig, Result};
+
+/// Signal metadata key holding the group a forward was addressed to
+pub const ROUTING_GROUP_KEY: &str = keys::routing::GROUP;
+/// Signal metadata key holding the neuron a policy chose
+pub const ROUTING_ROUTE_KEY: &str = keys::routing::ROUTE;
+/// Signal metadata key holding the strategy that chose the route
+pub const ROUTING_STRATEGY_KEY: &str = keys::routing::STRATEGY;
+/// Signal metadata key holding the content match rule that chose the route
+pub const ROUTING_RULE_KEY: &str = keys::routing::RULE;
+
+/// Keys [`Route::apply_to`] writes; they describe one forward, so children
+/// do not inherit them
+pub const ROUTE_KEYS: [&str; 4] = [ROUTING_GROUP_KEY, ROUTING_ROUTE_KEY, ROUTING_STRATEGY_KEY, ROUTING_RULE_KEY];
+
+/// Group a forward naming no neuron is addressed to
+pub const ANY_TARGET: &str = "*";
+
+/// Rule recorded when no content match rule matched
+pub const FALLBACK_RULE: &str = "fallback";
+
+/// Dimension of the embeddings content is compared to rule examples with
+const EMBEDDING_DIMENSION: usize = 256;
+
+/// How a policy picks a target
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RoutingStrategy {
+    ContentMatch,
+    RoundRobin,
+    LeastLoaded,
+    Static,
+}
+
+impl RoutingStrategy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RoutingStrategy::ContentMatch => "content_match",
+            RoutingStrategy::RoundRobin => "round_robin",
+            RoutingStrategy::LeastLoaded => "least_loaded",
+            RoutingStrategy::Static => "static",
+        }
+    }
+}
+
+/// A content match rule of `settings.routing.rules`
+#[derive(Debug, Clone, Deserialize)]
+pub struct RoutingRuleConfig {
+    /// Name recorded in `routing.rule` when the rule matches
+    pub name: String,
+    pub target: String,
+    /// Words or phrases matched case-insensitively on word boundaries
+    #[serde(default)]
+    pub keywords: Vec<String>,
+    /// Regular expression matched against the content
+    #[serde(default)]
+    pub pattern: Option<String>,
+    /// Texts of the intent the rule stands for
+    #[serde(default)]
+    pub examples: Vec<String>,
+    /// Similarity to the closest example needed to match
+    #[serde(default = "default_min_similarity")]
+    pub min_similarity: f32,
+}
+
+fn default_min_similarity() -> f32 {
+    0.6
+}
+
+/// A neuron's `settings.routing`
+#[derive(Debug, Clone, Deserialize)]
+pub struct RoutingSettings {
+    pub strategy: RoutingStrategy,
+    /// Name a response's `FORWARD_TO:` line can give in place of a neuron
+    #[serde(default)]
+    pub group: Option<String>,
+    /// Neurons the policy picks from, all forward connections of the
+    /// neuron; defaults to every forward connection
+    #[serde(default)]
+    pub targets: Vec<String>,
+    /// Target of `static`, and of `content_match` when no rule matches;
+    /// defaults to the first target
+    #[serde(default)]
+    pub fallback: Option<String>,
+    #[serde(default)]
+    pub rules: Vec<RoutingRuleConfig>,
+}
+
+/// A rule with its matchers compiled
+struct RoutingRule {
+    name: String,
+    target: String,
+    keywords: Option<Regex>,
+    pattern: Option<Regex>,
+    examples: Vec<String>,
+    min_similarity: f32,
+}
+
+/// Where a policy sent a forward, and why
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Route {
+    /// Group the forward was addressed to
+    pub group: String,
+    pub target: String,
+    pub strategy: RoutingStrategy,
+    /// Content match rule that matched, or [`FALLBACK_RULE`]
+    pub rule: Option<String>,
+}
+
+impl Route {
+    /// Write the route into signal metadata
+    pub fn apply_to(&self, metadata: &mut HashMap<String, String>) {
+        metadata.insert(ROUTING_GROUP_KEY.to_string(), self.group.clone());
+        metadata.insert(ROUTING_ROUTE_KEY.to_string(), self.target.clone());
+        metadata.insert(ROUTING_STRATEGY_KEY.to_string(), self.strategy.as_str().to_string());
+        match &self.rule {
+            Some(rule) => metadata.insert(ROUTING_RULE_KEY.to_string(), rule.clone()),
+            None => metadata.remove(ROUTING_RULE_KEY),
+        };
+    }
+}
+
+/// A neuron's routing policy
+pub struct RoutingPolicy {
+    strategy: RoutingStrategy,
+    group: Option<String>,
+    targets: Vec<String>,
+    fallback: String,
+    rules: Vec<RoutingRule>,
+    /// Forwards routed by `round_robin` so far
+    turn: AtomicUsize,
+    embeddings: EmbeddingGenerator,
+}
+
+impl RoutingPolicy {
+    /// The neuron's `settings.routing`, if it has one
+    pub fn for_neuron(neuron: &NeuronConfig) -> Result<Option<Self>> {
+        let settings = match neuron.settings.get("routing") {
+            Some(settings) => serde_json::from_value::<RoutingSettings>(settings.clone()).map_err(|e| {
+                Error::Config(format!("Invalid routing settings for neuron {}: {}", neuron.id, e))
+            })?,
+            None => return Ok(None),
+        };
+        Self::new(neuron, settings).map(Some)
+    }
+
+    fn new(neuron: &NeuronConfig, settings: RoutingSettings) -> Result<Self> {
+        let invalid = |reason: String| Error::Config(format!("Invalid routing settings for neuron {}: {}", neuron.id, reason));
+        let targets = if settings.targets.is_empty() {
+            neuron.forward_connections.clone()
+        } else {
+            settings.targets
+        };
+        let group = settings.group.as_deref().unwrap_or(ANY_TARGET);
+        if targets.is_empty() {
+            return Err(invalid(format!("routing group {} resolves to no neuron", group)));
+        }
+        if let Some(target) = targets.iter().find(|target| !neuron.forward_connections.contains(target)) {
+            return Err(invalid(format!("target {} is not a forward connection", target)));
+        }
+        if let Some(group) = &settings.group {
+            if group.is_empty() || group == ANY_TARGET || neuron.forward_connections.contains(group) {
+                return Err(invalid(format!("group name '{}' is empty, reserved or a neuron ID", group)));
+            }
+        }
+        let fallback = settings.fallback.unwrap_or_else(|| targets[0].clone());
+        if !targets.contains(&fallback) {
+            return Err(invalid(format!("fallback {} is not a target", fallback)));
+        }
+        if settings.strategy != RoutingStrategy::ContentMatch && !settings.rules.is_empty() {
+            return Err(invalid(format!("rules need the content_match strategy, not {}", settings.strategy.as_str())));
+        }
+
+        let rules = settings.rules.into_iter()
+            .map(|rule| {
+                if !targets.contains(&rule.target) {
+                    return Err(invalid(format!("rule {} routes to {}, which is not a target", rule.name, rule.target)));
+                }
+                if rule.keywords.is_empty() && rule.pattern.is_none() && rule.examples.is_empty() {
+                    return Err(invalid(format!("rule {} has no keywords, pattern or examples", rule.name)));
+                }
+                if !(rule.min_similarity > 0.0 && rule.min_similarity <= 1.0) {
+                    return Err(invalid(format!("rule {} needs a min_similarity between 0 and 1", rule.name)));
+                }
+                let keywords = (!rule.keywords.is_empty()).then(|| {
+                    let words: Vec<String> = rule.keywords.iter().map(|word| regex::escape(word.trim())).collect();
+                    Regex::new(&format!(r"(?i)\b(?:{})\b", words.join("|")))
+                })
+                .transpose()
+                .map_err(|e| invalid(format!("rule {} has invalid keywords: {}", rule.name, e)))?;
+                let pattern = rule.pattern.as_deref()
+                    .map(Regex::new)
+                    .transpose()
+                    .map_err(|e| invalid(format!("rule {} has an invalid pattern: {}", rule.name, e)))?;
+                Ok(RoutingRule {
+                    name: rule.name,
+                    target: rule.target,
+                    keywords,
+                    pattern,
+                    examples: rule.examples,
+                    min_similarity: rule.min_similarity,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self {
+            strategy: settings.strategy,
+            group: settings.group,
+            targets,
+            fallback,
+            rules,
+            turn: AtomicUsize::new(0),
+            embeddings: EmbeddingGenerator::new(EMBEDDING_DIMENSION),
+        })
+    }
+
+    pub fn strategy(&self) -> RoutingStrategy {
+        self.strategy
+    }
+
+    /// Name a forward is addressed to when left to this policy: the group,
+    /// or [`ANY_TARGET`] without one
+    pub fn address(&self) -> &str {
+        self.group.as_deref().unwrap_or(ANY_TARGET)
+    }
+
+    /// Whether a forward to `to_neuron` is left to this policy
+    pub fn routes(&self, to_neuron: &str) -> bool {
+        to_neuron == ANY_TARGET || self.group.as_deref() == Some(to_neuron)
+    }
+
+    /// Neurons the policy picks from, in declared order
+    pub fn targets(&self) -> &[String] {
+        &self.targets
+    }
+
+    /// Pick the target of a forward to `group` carrying `content`; `load`
+    /// gives the signals admitted by a neuron
+    pub async fn choose(&self, group: &str, content: &str, load: impl Fn(&str) -> usize) -> Route {
+        let (target, rule) = match self.strategy {
+            RoutingStrategy::ContentMatch => match self.best_rule(content).await {
+                Some(rule) => (rule.target.clone(), Some(rule.name.clone())),
+                None => (self.fallback.clone(), Some(FALLBACK_RULE.to_string())),
+            },
+            RoutingStrategy::RoundRobin => {
+                let turn = self.turn.fetch_add(1, Ordering::Relaxed);
+                (self.targets[turn % self.targets.len()].clone(), None)
+            }
+            // min_by_key keeps the first of equally loaded targets
+            RoutingStrategy::LeastLoaded => {
+                let target = self.targets.iter().min_by_key(|target| load(target)).unwrap_or(&self.fallback);
+                (target.clone(), None)
+            }
+            RoutingStrategy::Static => (self.fallback.clone(), None),
+        };
+        Route { group: group.to_string(), target, strategy: self.strategy, rule }
+    }
+
+    /// The rule scoring highest against `content`, the first declared on a
+    /// tie; `None` when none matches
+    async fn best_rule(&self, content: &str) -> Option<&RoutingRule> {
+        let mut content_embedding = None;
+        let mut best: Option<(&RoutingRule, f32)> = None;
+        for rule in &self.rules {
+            let matched = rule.keywords.as_ref().is_some_and(|keywords| keywords.is_match(content))
+                || rule.pattern.as_ref().is_some_and(|pattern| pattern.is_match(content));
+            let score = if matched {
+                1.0
+            } else if rule.examples.is_empty() {
+                continue;
+            } else {
+                if content_embedding.is_none() {
+                    content_embedding = self.embeddings.generate(content).await.ok();
+                }
+                let Some(content_embedding) = &content_embedding else { continue };
+                let mut closest = 0.0f32;
+                for example in &rule.examples {
+                    if let Ok(embedding) = self.embeddings.generate(example).await {
+                        closest = closest.max(EmbeddingGenerator::cosine_similarity(content_embedding, &embedding));
+                    }
+                }
+                if closest < rule.min_similarity {
+                    continue;
+                }
+                closest
+            };
+            if best.is_none_or(|(_, best_score)| score > best_score) {
+                best = Some((rule, score));
+            }
+        }
+        best.map(|(rule, _)| rule)
+    }
+}
@@ -0,0 +1,315 @@
+//! Replica autoscaling signals and drain coordination
+//!
+//! CPU says little about how loaded a HAL9 replica is: most of a signal's
+//! life is spent waiting for a processing slot or for Claude. The load
+//! signals here are what an external autoscaler should scale on instead:
+//! signals waiting to be processed, how long the oldest of them has waited,
+//! and how many of the processing slots (and with them the Claude
+//! connections) are taken.
+//!
+//! [`recommend`] turns them into a replica count. It assumes the load
+//! balancer spreads work evenly, so this replica's load stands for the
+//! average across replicas.
+//!
+//! A replica being terminated drains first: it reports not ready, refuses
+//! new chains and waits for the chains it is running to finish before it
+//! reports safe to terminate.
+
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use hal9_core::config::AutoscalingConfig;
+
+use crate::chain_tracker::ChainTracker;
+use crate::fair_scheduler::FairScheduler;
+
+/// How often a draining server rechecks whether its work has finished
+const DRAIN_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Signals received by the router that no neuron has started processing
+#[derive(Default)]
+pub struct PendingSignals {
+    waiting: DashMap<Uuid, Instant>,
+}
+
+/// A signal counted as pending; it stops counting when this is dropped
+pub struct PendingGuard {
+    pending: Arc<PendingSignals>,
+    signal_id: Uuid,
+}
+
+impl Drop for PendingGuard {
+    fn drop(&mut self) {
+        self.pending.waiting.remove(&self.signal_id);
+    }
+}
+
+impl PendingSignals {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start counting a signal as pending
+    pub fn enter(&self, signal_id: Uuid) {
+        self.waiting.insert(signal_id, Instant::now());
+    }
+
+    /// Keep counting a signal as pending, from when it entered if it did,
+    /// until the guard is dropped
+    pub fn guard(self: &Arc<Self>, signal_id: Uuid) -> PendingGuard {
+        self.waiting.entry(signal_id).or_insert_with(Instant::now);
+        PendingGuard {
+            pending: self.clone(),
+            signal_id,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.waiting.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.waiting.is_empty()
+    }
+
+    /// How long the longest-waiting signal has waited
+    pub fn oldest_age(&self) -> Duration {
+        self.waiting.iter()
+            .map(|entry| entry.value().elapsed())
+            .max()
+            .unwrap_or_default()
+    }
+}
+
+/// Current load of this replica
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LoadSignals {
+    pub pending_signals: usize,
+    pub oldest_pending_age_secs: f64,
+    /// Share of processing slots in use
+    pub pool_saturation: f64,
+    pub slots_in_use: usize,
+    pub slots_capacity: usize,
+    pub running_chains: usize,
+    pub draining: bool,
+}
+
+/// Load signal a recommendation was driven by
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScalingDriver {
+    QueueAge,
+    PendingSignals,
+    PoolSaturation,
+}
+
+/// Suggested replica count
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ScalingRecommendation {
+    pub current_replicas: u32,
+    pub recommended_replicas: u32,
+    /// Signal with the highest ratio to its target, `None` without load
+    pub driver: Option<ScalingDriver>,
+    /// That signal's current value over its target
+    pub ratio: f64,
+    pub load: LoadSignals,
+}
+
+/// Recommend a replica count for `load` seen with `current_replicas` running.
+///
+/// Each signal's ratio to its target scales the current count; the highest
+/// ratio wins, so any one signal over target scales up and all must be under
+/// to scale down. Ratios within `tolerance` of 1.0 keep the current count.
+pub fn recommend(config: &AutoscalingConfig, current_replicas: u32, load: &LoadSignals) -> ScalingRecommendation {
+    let current = current_replicas.max(1);
+    let ratios = [
+        (ScalingDriver::QueueAge, load.oldest_pending_age_secs, config.target_queue_age_secs),
+        (ScalingDriver::PendingSignals, load.pending_signals as f64, config.target_pending_signals),
+        (ScalingDriver::PoolSaturation, load.pool_saturation, config.target_saturation),
+    ];
+    let (driver, ratio) = ratios.iter()
+        .filter(|(_, _, target)| *target > 0.0)
+        .map(|(driver, value, target)| (*driver, value / target))
+        .fold((None, 0.0), |best, (driver, ratio)| {
+            if ratio > best.1 { (Some(driver), ratio) } else { best }
+        });
+
+    let desired = if (ratio - 1.0).abs() <= config.tolerance {
+        current
+    } else {
+        (current as f64 * ratio).ceil() as u32
+    };
+    let max_replicas = config.max_replicas.max(config.min_replicas);
+
+    ScalingRecommendation {
+        current_replicas: current,
+        recommended_replicas: desired.clamp(config.min_replicas, max_replicas),
+        driver,
+        ratio,
+        load: load.clone(),
+    }
+}
+
+/// Progress of a drain
+#[derive(Debug, Clone, Serialize)]
+pub struct DrainStatus {
+    pub draining: bool,
+    pub since: Option<DateTime<Utc>>,
+    pub running_chains: usize,
+    pub pending_signals: usize,
+    /// Draining and nothing left to finish
+    pub safe_to_terminate: bool,
+}
+
+/// How a drain ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DrainOutcome {
+    /// All work finished
+    Drained,
+    /// The drain timeout passed with work still running
+    TimedOut,
+}
+
+/// Load signals of one replica and the drain state of its shutdown
+pub struct Autoscaler {
+    config: AutoscalingConfig,
+    pending: Arc<PendingSignals>,
+    scheduler: Arc<FairScheduler>,
+    chain_tracker: Arc<ChainTracker>,
+    draining_since: Mutex<Option<DateTime<Utc>>>,
+}
+
+impl Autoscaler {
+    pub fn new(
+        config: AutoscalingConfig,
+        pending: Arc<PendingSignals>,
+        scheduler: Arc<FairScheduler>,
+        chain_tracker: Arc<ChainTracker>,
+    ) -> Self {
+        Self {
+            config,
+            pending,
+            scheduler,
+            chain_tracker,
+            draining_since: Mutex::new(None),
+        }
+    }
+
+    pub fn config(&self) -> &AutoscalingConfig {
+        &self.config
+    }
+
+    /// Pending signals, for the router to count
+    pub fn pending(&self) -> Arc<PendingSignals> {
+        self.pending.clone()
+    }
+
+    /// Current load of this replica
+    pub fn load(&self) -> LoadSignals {
+        let slots = self.scheduler.stats();
+        LoadSignals {
+            pending_signals: self.pending.len(),
+            oldest_pending_age_secs: self.pending.oldest_age().as_secs_f64(),
+            pool_saturation: slots.in_use as f64 / slots.capacity as f64,
+            slots_in_use: slots.in_use,
+            slots_capacity: slots.capacity,
+            running_chains: self.chain_tracker.running_count(),
+            draining: self.is_draining(),
+        }
+    }
+
+    /// Replica count recommended for the current load
+    pub fn recommend(&self, current_replicas: u32) -> ScalingRecommendation {
+        recommend(&self.config, current_replicas, &self.load())
+    }
+
+    /// Enter drain mode; returns false if already draining
+    pub fn begin_drain(&self) -> bool {
+        let mut since = self.draining_since.lock();
+        if since.is_some() {
+            return false;
+        }
+        *since = Some(Utc::now());
+        info!(
+            running_chains = self.chain_tracker.running_count(),
+            pending_signals = self.pending.len(),
+            "Draining: refusing new chains until running chains finish"
+        );
+        true
+    }
+
+    pub fn is_draining(&self) -> bool {
+        self.draining_since.lock().is_some()
+    }
+
+    pub fn drain_status(&self) -> DrainStatus {
+        let since = *self.draining_since.lock();
+        let running_chains = self.chain_tracker.running_count();
+        let pending_signals = self.pending.len();
+        DrainStatus {
+            draining: since.is_some(),
+            since,
+            running_chains,
+            pending_signals,
+            safe_to_terminate: since.is_some() && running_chains == 0 && pending_signals == 0,
+        }
+    }
+
+    /// Drain and wait until running chains finish, or the drain timeout
+    pub async fn drain(&self) -> DrainOutcome {
+        self.begin_drain();
+        let deadline = Instant::now() + Duration::from_secs(self.config.drain_timeout_secs);
+        loop {
+            let status = self.drain_status();
+            if status.safe_to_terminate {
+                info!("Drained, safe to terminate");
+                return DrainOutcome::Drained;
+            }
+            if Instant::now() >= deadline {
+                warn!(
+                    running_chains = status.running_chains,
+                    pending_signals = status.pending_signals,
+                    "Drain timed out with work still running"
+                );
+                return DrainOutcome::TimedOut;
+            }
+            tokio::time::sleep(DRAIN_POLL_INTERVAL.min(deadline - Instant::now())).await;
+        }
+    }
+
+    /// Drain once `terminate` completes; the task ends when it is safe to
+    /// terminate
+    pub fn drain_on<F>(self: &Arc<Self>, terminate: F) -> JoinHandle<DrainOutcome>
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        let autoscaler = self.clone();
+        tokio::spawn(async move {
+            terminate.await;
+            autoscaler.drain().await
+        })
+    }
+
+    /// Drain when the process receives SIGTERM, as Kubernetes sends to a
+    /// pod it is terminating
+    #[cfg(unix)]
+    pub fn drain_on_sigterm(self: &Arc<Self>) -> std::io::Result<JoinHandle<DrainOutcome>> {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        let mut terminate = signal(SignalKind::terminate())?;
+        Ok(self.drain_on(async move {
+            terminate.recv().await;
+            info!("SIGTERM received");
+        }))
+    }
+}
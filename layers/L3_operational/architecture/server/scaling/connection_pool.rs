@@ -109,6 +109,7 @@ impl OptimizedConnectionPool {
             connection_timeout: config.connect_timeout,
             idle_timeout: config.idle_timeout,
             max_lifetime: config.max_lifetime,
+            sqlite: Default::default(),
         };
         
         let primary_pool = Arc::new(ResilientConnectionPool::new(primary_config, None).await?);
@@ -124,6 +125,7 @@ impl OptimizedConnectionPool {
                 connection_timeout: config.connect_timeout,
                 idle_timeout: config.idle_timeout,
                 max_lifetime: config.max_lifetime,
+                sqlite: Default::default(),
             };
             
             let pool = Arc::new(ResilientConnectionPool::new(replica_config, None).await?);
@@ -6,6 +6,7 @@ pub mod session_manager;
 pub mod connection_pool;
 pub mod geo_routing;
 pub mod health_check;
+pub mod autoscale;
 
 pub use sharding::{ShardingStrategy, ShardConfig, ShardingManager};
 pub use load_balancer::{LoadBalancer, LoadBalancingStrategy};
@@ -13,6 +14,7 @@ pub use session_manager::{DistributedSessionManager, Session};
 pub use connection_pool::{OptimizedConnectionPool, PoolConfig};
 pub use geo_routing::{GeoRouter, Region};
 pub use health_check::{HealthChecker, HealthStatus};
+pub use autoscale::{Autoscaler, PendingSignals, LoadSignals, ScalingDriver, ScalingRecommendation, DrainStatus, DrainOutcome};
 
 #[cfg(test)]
 mod tests;
\ No newline at end of file
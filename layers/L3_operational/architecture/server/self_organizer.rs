@@ -0,0 +1,461 @@
+//! Load-based self-organization
+//!
+//! [`LoadTracker`] bounds how many signals each neuron processes at once and
+//! records queue depth and latency. [`LoadSelfOrganizer`] periodically checks
+//! every layer: when its queue depth or p95 latency stays above thresholds it
+//! clones one of the layer's neurons and lets the router balance signals
+//! across the siblings; when load subsides it drains and removes the clones
+//! again. Scaling actions are rate-limited per layer and kept as events.
+
+use dashmap::DashMap;
+use futures::future::BoxFuture;
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tracing::{info, warn};
+
+use hal9_core::{config::ScalingConfig, Layer, NeuronConfig, Result};
+
+use crate::{
+    neuron::{ManagedNeuron, NeuronRegistry},
+    router::RoutingTable,
+};
+
+/// Scaling events kept for the API
+const MAX_EVENTS: usize = 500;
+
+/// Latency samples kept per neuron, whatever the window
+const MAX_SAMPLES: usize = 1000;
+
+/// Builds a neuron from a config, with the same provider setup as at startup
+pub type NeuronFactory = Arc<dyn Fn(NeuronConfig) -> Result<ManagedNeuron> + Send + Sync>;
+
+/// Fraction of the cost budget spent so far, from 0.0 to 1.0
+pub type BudgetProbe = Arc<dyn Fn() -> BoxFuture<'static, f64> + Send + Sync>;
+
+/// Load of a single neuron
+struct NeuronLoad {
+    slots: Arc<Semaphore>,
+    /// Signals waiting for or holding a slot
+    queued: AtomicUsize,
+    /// (completed at, latency in ms)
+    latencies: Mutex<VecDeque<(Instant, u64)>>,
+}
+
+/// Per-neuron concurrency limits, queue depth and latency
+pub struct LoadTracker {
+    neurons: DashMap<String, Arc<NeuronLoad>>,
+    concurrency: usize,
+    window: Duration,
+}
+
+/// Held while a signal is queued for or processed by a neuron
+pub struct LoadGuard {
+    load: Arc<NeuronLoad>,
+    started: Instant,
+    _permit: Option<OwnedSemaphorePermit>,
+}
+
+impl Drop for LoadGuard {
+    fn drop(&mut self) {
+        self.load.queued.fetch_sub(1, Ordering::SeqCst);
+        let mut latencies = self.load.latencies.lock();
+        latencies.push_back((Instant::now(), self.started.elapsed().as_millis() as u64));
+        if latencies.len() > MAX_SAMPLES {
+            latencies.pop_front();
+        }
+    }
+}
+
+impl LoadTracker {
+    /// Allow `concurrency` signals per neuron, keeping latencies for `window`
+    pub fn new(concurrency: usize, window: Duration) -> Self {
+        Self {
+            neurons: DashMap::new(),
+            concurrency: concurrency.max(1),
+            window,
+        }
+    }
+
+    fn load(&self, neuron_id: &str) -> Arc<NeuronLoad> {
+        self.neurons.entry(neuron_id.to_string())
+            .or_insert_with(|| Arc::new(NeuronLoad {
+                slots: Arc::new(Semaphore::new(self.concurrency)),
+                queued: AtomicUsize::new(0),
+                latencies: Mutex::new(VecDeque::new()),
+            }))
+            .clone()
+    }
+
+    /// Queue a signal for `neuron_id` and wait for one of its slots
+    pub async fn begin(&self, neuron_id: &str) -> LoadGuard {
+        let load = self.load(neuron_id);
+        let started = Instant::now();
+        load.queued.fetch_add(1, Ordering::SeqCst);
+        let permit = load.slots.clone().acquire_owned().await.ok();
+        LoadGuard {
+            load,
+            started,
+            _permit: permit,
+        }
+    }
+
+    /// Signals waiting for or being processed by `neuron_id`
+    pub fn queue_depth(&self, neuron_id: &str) -> usize {
+        self.neurons.get(neuron_id)
+            .map(|load| load.queued.load(Ordering::SeqCst))
+            .unwrap_or(0)
+    }
+
+    /// 95th percentile latency across `neuron_ids` within the window
+    pub fn p95_ms(&self, neuron_ids: &[String]) -> Option<u64> {
+        let cutoff = Instant::now().checked_sub(self.window);
+        let mut samples: Vec<u64> = neuron_ids.iter()
+            .filter_map(|id| self.neurons.get(id).map(|load| load.clone()))
+            .flat_map(|load| {
+                load.latencies.lock().iter()
+                    .filter(|(at, _)| cutoff.is_none_or(|cutoff| *at >= cutoff))
+                    .map(|(_, ms)| *ms)
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        if samples.is_empty() {
+            return None;
+        }
+        samples.sort_unstable();
+        let index = ((samples.len() as f64 * 0.95).ceil() as usize).saturating_sub(1);
+        Some(samples[index.min(samples.len() - 1)])
+    }
+
+    /// Forget a removed neuron
+    pub fn remove(&self, neuron_id: &str) {
+        self.neurons.remove(neuron_id);
+    }
+}
+
+/// Kind of scaling decision
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScalingAction {
+    ScaleUp,
+    ScaleDown,
+    /// Scaling up was warranted but a guardrail prevented it
+    ScaleUpSkipped,
+}
+
+/// A scaling decision, as returned by `GET /api/v1/scaling/events`
+#[derive(Debug, Clone, Serialize)]
+pub struct ScalingEvent {
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+    pub layer: String,
+    pub action: ScalingAction,
+    /// Neuron that was cloned or whose clone was removed
+    pub source_neuron: String,
+    /// The clone added or removed
+    pub clone_id: Option<String>,
+    pub reason: String,
+    pub queue_depth: usize,
+    pub p95_latency_ms: Option<u64>,
+    /// Neurons in the layer after the action
+    pub layer_size: usize,
+}
+
+/// Load snapshot of one layer
+#[derive(Debug, Clone)]
+struct LayerLoad {
+    neurons: Vec<String>,
+    queue_depth: usize,
+    p95_ms: Option<u64>,
+}
+
+#[derive(Default)]
+struct LayerState {
+    hot_since: Option<Instant>,
+    cold_since: Option<Instant>,
+    last_action: Option<Instant>,
+}
+
+#[derive(Default)]
+struct OrganizerState {
+    layers: HashMap<Layer, LayerState>,
+    /// Live clones by the neuron they were cloned from, oldest first
+    clones: HashMap<String, Vec<String>>,
+    next_clone: u64,
+}
+
+/// Scales layers by cloning neurons under sustained load
+pub struct LoadSelfOrganizer {
+    config: ScalingConfig,
+    registry: Arc<NeuronRegistry>,
+    routing_table: Arc<RoutingTable>,
+    load: Arc<LoadTracker>,
+    factory: NeuronFactory,
+    budget: Option<BudgetProbe>,
+    state: tokio::sync::Mutex<OrganizerState>,
+    events: Mutex<VecDeque<ScalingEvent>>,
+}
+
+impl LoadSelfOrganizer {
+    /// Create an organizer that builds clones with `factory`
+    pub fn new(
+        config: ScalingConfig,
+        registry: Arc<NeuronRegistry>,
+        routing_table: Arc<RoutingTable>,
+        load: Arc<LoadTracker>,
+        factory: NeuronFactory,
+    ) -> Self {
+        Self {
+            config,
+            registry,
+            routing_table,
+            load,
+            factory,
+            budget: None,
+            state: tokio::sync::Mutex::new(OrganizerState::default()),
+            events: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// Set how much of the cost budget is spent; scaling up stops near the limit
+    pub fn set_budget_probe(&mut self, budget: BudgetProbe) {
+        self.budget = Some(budget);
+    }
+
+    /// Scaling events, newest first
+    pub fn events(&self) -> Vec<ScalingEvent> {
+        self.events.lock().iter().rev().cloned().collect()
+    }
+
+    /// Clones currently alive in a layer
+    pub async fn clone_count(&self, layer: Layer) -> usize {
+        let state = self.state.lock().await;
+        state.clones.values().flatten()
+            .filter(|id| self.registry.get(id).map(|n| n.layer == layer).unwrap_or(false))
+            .count()
+    }
+
+    fn layer_load(&self, layer: Layer) -> LayerLoad {
+        let mut neurons: Vec<String> = self.registry.by_layer(layer).iter()
+            .map(|neuron| neuron.id.clone())
+            .collect();
+        neurons.sort();
+        LayerLoad {
+            queue_depth: neurons.iter().map(|id| self.load.queue_depth(id)).sum(),
+            p95_ms: self.load.p95_ms(&neurons),
+            neurons,
+        }
+    }
+
+    fn record(&self, event: ScalingEvent) {
+        info!(
+            "Scaling {:?} on {}: {} (clone {:?}, queue {}, p95 {:?}ms)",
+            event.action, event.layer, event.reason, event.clone_id, event.queue_depth, event.p95_latency_ms
+        );
+        let mut events = self.events.lock();
+        events.push_back(event);
+        if events.len() > MAX_EVENTS {
+            events.pop_front();
+        }
+    }
+
+    /// Evaluate every layer once and scale where load has been sustained
+    pub async fn evaluate(self: &Arc<Self>) {
+        let mut layers: Vec<Layer> = self.registry.all().iter().map(|n| n.layer).collect();
+        layers.sort_by_key(|layer| layer.as_str());
+        layers.dedup();
+
+        let now = Instant::now();
+        let sustained = Duration::from_millis(self.config.sustained_ms);
+        let cooldown = Duration::from_millis(self.config.cooldown_ms);
+
+        for layer in layers {
+            let load = self.layer_load(layer);
+            let hot = load.queue_depth > self.config.scale_up_queue_depth
+                || load.p95_ms.is_some_and(|p95| p95 > self.config.scale_up_p95_ms);
+            let cold = load.queue_depth <= self.config.scale_down_queue_depth
+                && load.p95_ms.is_none_or(|p95| p95 <= self.config.scale_down_p95_ms);
+
+            let mut state = self.state.lock().await;
+            let layer_state = state.layers.entry(layer).or_default();
+            layer_state.hot_since = if hot { layer_state.hot_since.or(Some(now)) } else { None };
+            layer_state.cold_since = if cold { layer_state.cold_since.or(Some(now)) } else { None };
+
+            let cooled_down = layer_state.last_action
+                .is_none_or(|at| now.duration_since(at) >= cooldown);
+            let hot_for = layer_state.hot_since.map(|since| now.duration_since(since));
+            let cold_for = layer_state.cold_since.map(|since| now.duration_since(since));
+
+            if !cooled_down {
+                continue;
+            }
+            if hot_for.is_some_and(|d| d >= sustained) {
+                layer_state.last_action = Some(now);
+                layer_state.hot_since = None;
+                self.scale_up(&mut state, layer, &load).await;
+            } else if cold_for.is_some_and(|d| d >= sustained) {
+                if let Some(acted) = self.scale_down(&mut state, layer, &load) {
+                    let layer_state = state.layers.entry(layer).or_default();
+                    layer_state.last_action = Some(acted);
+                    layer_state.cold_since = None;
+                }
+            }
+        }
+    }
+
+    async fn scale_up(&self, state: &mut OrganizerState, layer: Layer, load: &LayerLoad) {
+        let clones_in_layer = load.neurons.iter()
+            .filter(|id| state.clones.values().flatten().any(|clone| clone == *id))
+            .count();
+
+        // Clone the busiest original neuron of the layer
+        let Some(source) = load.neurons.iter()
+            .filter(|id| !state.clones.values().flatten().any(|clone| clone == *id))
+            .max_by_key(|id| {
+                self.routing_table.siblings(id).iter()
+                    .map(|sibling| self.load.queue_depth(sibling))
+                    .sum::<usize>()
+            })
+            .cloned()
+        else {
+            return;
+        };
+
+        let reason = format!(
+            "queue depth {} (limit {}), p95 {:?}ms (limit {}ms)",
+            load.queue_depth, self.config.scale_up_queue_depth,
+            load.p95_ms, self.config.scale_up_p95_ms
+        );
+        let skipped = |why: String| ScalingEvent {
+            timestamp: chrono::Utc::now(),
+            layer: layer.as_str().to_string(),
+            action: ScalingAction::ScaleUpSkipped,
+            source_neuron: source.clone(),
+            clone_id: None,
+            reason: why,
+            queue_depth: load.queue_depth,
+            p95_latency_ms: load.p95_ms,
+            layer_size: load.neurons.len(),
+        };
+
+        if clones_in_layer >= self.config.max_clones_per_layer {
+            self.record(skipped(format!(
+                "{}; layer already has the maximum of {} clones",
+                reason, self.config.max_clones_per_layer
+            )));
+            return;
+        }
+        if let Some(budget) = &self.budget {
+            let used = budget().await;
+            if used >= self.config.max_budget_utilization {
+                self.record(skipped(format!(
+                    "{}; {:.0}% of the cost budget is spent (limit {:.0}%)",
+                    reason, used * 100.0, self.config.max_budget_utilization * 100.0
+                )));
+                return;
+            }
+        }
+
+        let Some(neuron) = self.registry.get(&source) else {
+            return;
+        };
+        state.next_clone += 1;
+        let clone_id = format!("{}-clone-{}", source, state.next_clone);
+        let mut config = neuron.config.clone();
+        config.id = clone_id.clone();
+
+        let created = match (self.factory)(config) {
+            Ok(clone) => self.registry.register(clone).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = created {
+            warn!("Failed to clone neuron {}: {}", source, e);
+            self.record(skipped(format!("{}; cloning failed: {}", reason, e)));
+            return;
+        }
+
+        self.routing_table.add_sibling(&source, &clone_id);
+        state.clones.entry(source.clone()).or_default().push(clone_id.clone());
+        self.record(ScalingEvent {
+            timestamp: chrono::Utc::now(),
+            layer: layer.as_str().to_string(),
+            action: ScalingAction::ScaleUp,
+            source_neuron: source,
+            clone_id: Some(clone_id),
+            reason,
+            queue_depth: load.queue_depth,
+            p95_latency_ms: load.p95_ms,
+            layer_size: load.neurons.len() + 1,
+        });
+    }
+
+    /// Stop routing to the layer's newest clone and remove it once drained
+    fn scale_down(self: &Arc<Self>, state: &mut OrganizerState, layer: Layer, load: &LayerLoad) -> Option<Instant> {
+        if load.neurons.len() <= self.config.min_neurons_per_layer {
+            return None;
+        }
+
+        let (source, clone_id) = state.clones.iter()
+            .flat_map(|(source, clones)| clones.iter().map(move |clone| (source, clone)))
+            .filter(|(_, clone)| load.neurons.contains(clone))
+            .max_by_key(|(_, clone)| clone_number(clone))
+            .map(|(source, clone)| (source.clone(), clone.clone()))?;
+
+        self.routing_table.remove_sibling(&source, &clone_id);
+        if let Some(clones) = state.clones.get_mut(&source) {
+            clones.retain(|id| id != &clone_id);
+        }
+        state.clones.retain(|_, clones| !clones.is_empty());
+
+        self.record(ScalingEvent {
+            timestamp: chrono::Utc::now(),
+            layer: layer.as_str().to_string(),
+            action: ScalingAction::ScaleDown,
+            source_neuron: source,
+            clone_id: Some(clone_id.clone()),
+            reason: format!(
+                "queue depth {} (limit {}), p95 {:?}ms (limit {}ms)",
+                load.queue_depth, self.config.scale_down_queue_depth,
+                load.p95_ms, self.config.scale_down_p95_ms
+            ),
+            queue_depth: load.queue_depth,
+            p95_latency_ms: load.p95_ms,
+            layer_size: load.neurons.len() - 1,
+        });
+
+        // No new signals reach the clone; let in-flight ones finish first
+        let organizer = self.clone();
+        tokio::spawn(async move {
+            let deadline = Instant::now() + Duration::from_millis(organizer.config.drain_timeout_ms);
+            while organizer.load.queue_depth(&clone_id) > 0 && Instant::now() < deadline {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+            }
+            if let Err(e) = organizer.registry.remove(&clone_id).await {
+                warn!("Failed to remove drained clone {}: {}", clone_id, e);
+            }
+            organizer.load.remove(&clone_id);
+        });
+
+        Some(Instant::now())
+    }
+}
+
+/// Sequence number of a clone ID, so the newest clone is removed first
+fn clone_number(clone_id: &str) -> u64 {
+    clone_id.rsplit('-').next()
+        .and_then(|n| n.parse().ok())
+        .unwrap_or(0)
+}
+
+/// Evaluate layer load periodically
+pub async fn scaling_task(organizer: Arc<LoadSelfOrganizer>) {
+    let mut interval = tokio::time::interval(Duration::from_millis(organizer.config.evaluation_interval_ms.max(10)));
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+    loop {
+        interval.tick().await;
+        organizer.evaluate().await;
+    }
+}
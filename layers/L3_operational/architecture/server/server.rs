@@ -1,21 +1,96 @@
 //! Main 2HAL9 server implementation
 
+use std::collections::HashSet;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
+use async_trait::async_trait;
+use uuid::Uuid;
 use tokio::sync::{RwLock, broadcast};
-use tracing::{info, error};
+use tracing::{debug, info, warn, error};
 
-use hal9_core::{Error, Result, ServerConfig, NeuronSignal, neuron::NeuronHealth};
-use hal9_core::auth::{UserManager, JwtManager, ApiKeyManager};
+use hal9_core::{Error, Result, ServerConfig, NeuronConfig, NeuronSignal, PropagationType, neuron::{NeuronHealth, NeuronState}};
+use hal9_core::config::{BudgetPeriod, ClaudeConfig, IsolationMode, MemoryValidatorConfig};
+use hal9_core::config_layers::{EffectiveValue, LayeredConfig};
+use hal9_core::auth::{UserManager, JwtManager, ApiKeyManager, Permission};
+use hal9_api_types::SubmitTemplateRequest;
+use hal9_core::metadata_schema::{self, SchemaDescription};
+use hal9_core::sqlite::{CompactReport, SqlitePools, SqliteTuning};
+use hal9_core::memory::{EmbeddingGenerator, MemoryEntry, MemoryNamespace, MemorySearch, NamespacedMemory, NamespaceStats};
+use hal9_core::consciousness::LayerTraffic;
+use hal9_core::encryption::{OrgKeyStatus, TenantKeyring};
+use hal9_core::hierarchical::intelligence::{
+    Challenge, CreationReport, DefaultIntelligenceCoordinator, EmergenceReport, IntelligenceCoordinator,
+    LayeredCreativityConfig, LayeredCreativityEngine, SignalFlowDetectorConfig, SignalFlowHistory,
+    Goal, StrategyLibrary,
+};
 use crate::{
     api::WsMessage,
-    claude::{ClaudeInterface, MockClaude, ClaudeAPIClient, FallbackClaude, HybridClaude},
-    cost_tracker::CostTracker,
+    auth_middleware::AuthUser,
+    approvals::{self, ApprovalDecision, ApprovalGates, PendingApproval},
+    event_stream::EventLog,
+    chain_tracker::{
+        ChainTracker, ChainRecord, ChainResult, SignalActivity, USER_ID_KEY, ORG_ID_KEY, API_KEY_ID_KEY, CHAIN_ID_KEY, PARENT_ID_KEY, REPLAY_OF_KEY,
+    },
+    chain_compare::ChainComparison,
+    chain_visualization::{ChainGraph, MAX_NODES},
+    chain_limits::{ChainLimiter, ChainOwner, LimitUsage},
+    chaos::{ChaosEngine, ChaosInjection, ChaosRequest, ChaosStatus},
+    mock_scenarios::{self, MockScenarios, RecordingSaved, ScenarioStatus},
+    codegen_jobs::CodegenJobs,
+    fair_scheduler::FairScheduler,
+    fan_out::{self, FanOutPolicy},
+    tool_sandbox::{self, ToolSandbox},
+    isolation::{NeuronWorker, WorkerEvent, WorkerEventKind},
+    degraded::{DegradedMode, DegradedStatus},
+    report_privacy::ReportPrivacy,
+    health::{ClaudeProbe, DatabaseProbe, DegradedProbe, DiskProbe, HealthChecker, LayerPauseProbe, MemoryBackendProbe, NeuronsProbe, ReadOnlyProbe, RedisProbe, SystemMemoryProbe},
+    layer_pause::{LayerGate, LayerPause, PausedLayerStatus},
+    read_only::{ReadOnlyGate, ReadOnlyState, ReadOnlyStatus},
+    retention::{self, RetentionJanitor, RetentionReport, RetentionStatus},
+    time_travel::{self, PointInTime, StateSnapshot, TimeTravel},
+    timeouts::{self, TimeoutPolicy, TimeoutStatus},
+    model_registry::{self, ModelRegistry},
+    database_migrations,
+    database_runtime::{DatabaseType, RuntimeDatabase},
+    memory_manager::{self, DumpScope, ImportOptions, ImportReport},
+    memory_tiering::{self, TieringJob, TieringReport, TieringStatus},
+    memory_summarization::{self, ModelSummarizer, SummarizationJob, SummarizationReport, SummarizationStatus},
+    memory_review::JudgeReviewer,
+    concurrency::DeadLetter,
+    self_organizer::{LoadSelfOrganizer, LoadTracker, NeuronFactory, ScalingEvent, scaling_task},
+    shadow::{self, ShadowMirror, ShadowStore},
+    performance::{self, Profiler},
+    prompt_composition::{self, PromptAccounting, PromptCompositionReport},
+    simulation::{SimRng, Simulation},
+    webhooks::WebhookManager,
+    org_usage::{self, OrgUsage, OrgUsageStore},
+    billing_export::{self, BillingExporter, Destination, ExportReport, ExportedFile},
+    goals::{GoalExecutor, GoalManager, GoalStatus},
+    ingestion::{self, Ingestion, SourceStatus, quarantine::QuarantinedMessage},
+    quality::{self, Benchmark, NewBenchmark, QualityHarness, QualityRun, QualityTrends, RunTrigger},
+    receipts::{ReceiptManager, ReceiptResponse},
+    recovery::RecoveryPlaybook,
+    sessions::{self, Session, SessionManager, SessionView, SESSION_CONTEXT_KEY, SESSION_ID_KEY},
+    singularity::{self, SingularityBridge},
+    slo::{self, SloStatus, SloTracker},
+    speculation::{self, Speculator},
+    templates::{self, ChainTemplate, NewTemplate, RenderedTemplate, TemplateLibrary, Artifact},
+    tenant_encryption,
+    local_model::{LocalModelClaude, LocalModels},
+    claude::{ClaudeInterface, ClaudeLayerPrompter, MockClaude, ClaudeAPIClient, FallbackClaude, HybridClaude},
+    cost_tracker::{CostStats, CostTracker},
+    cost_tags::{self, TagSpendReport, TAGS_KEY},
     error::{ServerError, ServerResult},
     neuron::{ManagedNeuron, NeuronRegistry},
+    neuron_changes::NeuronChanges,
+    output_format::FormatRequest,
+    validation::ValidationPipeline,
     router::{SignalRouter, RoutingTable, DistributedRouter, DistributedConfig},
+    scaling::{Autoscaler, PendingSignals},
     metrics::Metrics,
     network::{TcpTransport, ServiceDiscovery},
+    topology::{AppliedPatch, PatchOp, PatchProposal, PreparedPatch, TopologyEditor, PATCH_TOKEN_TTL},
+    warm_pool::WarmPoolStatus,
 };
 
 /// Network status information
@@ -27,69 +102,462 @@ pub struct NetworkStatus {
     pub remote_neurons: usize,
 }
 
+/// Pending memory writes the review queue endpoint pages through
+const MAX_PENDING_LISTED: usize = 10_000;
+
+/// Signals and consciousness measurements kept for emergence detection
+const SIGNAL_FLOW_CAPACITY: usize = 10_000;
+
 /// Main HAL9 server
 pub struct HAL9Server {
     config: ServerConfig,
+    /// Layers of the config, with runtime overrides; `config` is the
+    /// config at startup
+    config_layers: parking_lot::RwLock<LayeredConfig>,
     registry: Arc<NeuronRegistry>,
     routing_table: Arc<RoutingTable>,
-    router: RwLock<Option<SignalRouter>>,
-    distributed_router: RwLock<Option<Arc<DistributedRouter>>>,
+    router: Arc<RwLock<Option<SignalRouter>>>,
+    distributed_router: Arc<RwLock<Option<Arc<DistributedRouter>>>>,
     transport: RwLock<Option<Arc<TcpTransport>>>,
     discovery: RwLock<Option<Arc<RwLock<ServiceDiscovery>>>>,
     metrics: Arc<Metrics>,
     cost_tracker: Arc<CostTracker>,
-    event_tx: broadcast::Sender<WsMessage>,
+    chain_tracker: Arc<ChainTracker>,
+    chain_limiter: Arc<ChainLimiter>,
+    scheduler: Arc<FairScheduler>,
+    webhooks: Arc<WebhookManager>,
+    local_models: Arc<LocalModels>,
+    codegen_jobs: Arc<CodegenJobs>,
+    health: Arc<HealthChecker>,
+    memory: RwLock<Option<Arc<NamespacedMemory>>>,
+    /// Moves idle memory to the cold tier, once memory is started
+    memory_tiering: RwLock<Option<Arc<TieringJob>>>,
+    /// Summarizes memory namespaces near their quota, once memory is
+    /// started
+    memory_summarization: RwLock<Option<Arc<SummarizationJob>>>,
+    receipts: RwLock<Option<Arc<ReceiptManager>>>,
+    /// Data keys of organizations encrypted at rest, once started
+    keyring: RwLock<Option<Arc<TenantKeyring>>>,
+    /// Kafka and webhook sources, once started with any configured
+    ingestion: RwLock<Option<Arc<Ingestion>>>,
+    /// Quality benchmarks, once started with them enabled
+    quality: RwLock<Option<Arc<QualityHarness>>>,
+    /// Chain templates, once started with them enabled
+    templates: RwLock<Option<Arc<TemplateLibrary>>>,
+    /// Multi-turn conversations, once started with them enabled
+    sessions: RwLock<Option<Arc<SessionManager>>>,
+    /// Usage files for billing, once started with them enabled
+    billing_export: RwLock<Option<Arc<BillingExporter>>>,
+    layer_gate: Arc<LayerGate>,
+    /// Local heuristics and parked signals while every provider is down
+    degraded: Arc<DegradedMode>,
+    /// Noise and spent epsilon of exported usage reports
+    report_privacy: Arc<ReportPrivacy>,
+    /// Copies of sampled submissions sent to the staging server
+    shadow: Arc<ShadowMirror>,
+    /// CPU profiles and poll times of signal tasks
+    profiler: Arc<Profiler>,
+    read_only: Arc<ReadOnlyGate>,
+    retention: Arc<RetentionJanitor>,
+    /// State snapshots past instants are rebuilt from
+    time_travel: Arc<TimeTravel>,
+    timeouts: Arc<TimeoutPolicy>,
+    /// Prompt tokens by section, counted as neurons send them
+    prompt_accounting: Arc<PromptAccounting>,
+    /// Outputs held until someone approves them
+    approvals: Arc<ApprovalGates>,
+    models: Arc<ModelRegistry>,
+    fan_out: Arc<FanOutPolicy>,
+    /// Tool call ledgers of running chains
+    tool_sandbox: Arc<ToolSandbox>,
+    autoscaler: Arc<Autoscaler>,
+    slo: Arc<SloTracker>,
+    load_tracker: Arc<LoadTracker>,
+    self_organizer: RwLock<Option<Arc<LoadSelfOrganizer>>>,
+    /// Builds neurons once started; topology patches rebuild with it
+    neuron_factory: parking_lot::RwLock<Option<NeuronFactory>>,
+    topology: TopologyEditor,
+    /// Serialises putting topology patches in place
+    topology_apply: tokio::sync::Mutex<()>,
+    signal_flow: Arc<SignalFlowHistory>,
+    intelligence: DefaultIntelligenceCoordinator,
+    goals: Arc<GoalManager>,
+    submitter: SignalSubmitter,
+    simulation: Simulation,
+    chaos: Arc<ChaosEngine>,
+    /// Scenario files replayed by mock Claude, and the exchange recorder
+    mock_scenarios: Arc<MockScenarios>,
+    events: Arc<EventLog>,
+    /// Crashes and restarts of isolated neurons' worker processes
+    worker_events: broadcast::Sender<WorkerEvent>,
     start_time: RwLock<Option<Instant>>,
     // Authentication components
     pub user_manager: Option<Arc<UserManager>>,
     pub jwt_manager: Option<Arc<JwtManager>>,
     pub api_key_manager: Option<Arc<ApiKeyManager>>,
+    org_usage: Option<Arc<OrgUsageStore>>,
+}
+
+/// Sends and submits signals. Shared with components that start chains
+/// outside the API, such as goals.
+#[derive(Clone)]
+pub struct SignalSubmitter {
+    metrics: Arc<Metrics>,
+    cost_tracker: Arc<CostTracker>,
+    chain_tracker: Arc<ChainTracker>,
+    chain_limiter: Arc<ChainLimiter>,
+    autoscaler: Arc<Autoscaler>,
+    fan_out: Arc<FanOutPolicy>,
+    tool_sandbox: Arc<ToolSandbox>,
+    router: Arc<RwLock<Option<SignalRouter>>>,
+    distributed_router: Arc<RwLock<Option<Arc<DistributedRouter>>>>,
+    events: Arc<EventLog>,
+}
+
+impl SignalSubmitter {
+    /// Send a signal to the network
+    pub async fn send_signal(&self, signal: NeuronSignal) -> Result<()> {
+        self.metrics.record_signal_sent();
+        
+        // Use distributed router if available
+        if let Some(distributed_router) = self.distributed_router.read().await.as_ref() {
+            distributed_router.route_signal(signal).await
+        } else if let Some(router) = self.router.read().await.as_ref() {
+            router.send_signal(signal).await
+        } else {
+            Err(Error::InvalidState("Server not started".to_string()))
+        }
+    }
+    
+    /// Submit a signal as the root of a new chain, see [`HAL9Server::submit_signal_as`]
+    pub async fn submit_signal_as(&self, owner: Option<&ChainOwner>, mut signal: NeuronSignal) -> ServerResult<String> {
+        let signal_id = signal.signal_id.to_string();
+        
+        if self.autoscaler.is_draining() {
+            return Err(ServerError::Draining);
+        }
+        
+        metadata_schema::global()
+            .read()
+            .validate(&signal.metadata)
+            .map_err(|e| ServerError::InvalidInput(e.to_string()))?;
+        FormatRequest::from_metadata(&signal.metadata)
+            .map_err(|e| ServerError::InvalidInput(e.to_string()))?;
+        if let Some(tags) = signal.metadata.get(TAGS_KEY) {
+            let tags = cost_tags::decode(tags)
+                .and_then(|tags| self.cost_tracker.admit_tags(&tags).map(|_| tags))
+                .map_err(|e| ServerError::InvalidInput(e.to_string()))?;
+            signal.metadata.insert(TAGS_KEY.to_string(), cost_tags::encode(&tags));
+        }
+        
+        if let Some(owner) = owner {
+            signal.metadata.insert(USER_ID_KEY.to_string(), owner.user_id.clone());
+            if let Some(org_id) = &owner.org_id {
+                signal.metadata.insert(ORG_ID_KEY.to_string(), org_id.clone());
+            }
+            if let Some(api_key_id) = &owner.api_key_id {
+                signal.metadata.insert(API_KEY_ID_KEY.to_string(), api_key_id.clone());
+            }
+        }
+        
+        self.fan_out.start(&mut signal);
+        let chain_id = self.chain_tracker.start(&mut signal);
+        if let Some(owner) = owner {
+            if let Err(reason) = self.chain_limiter.admit(owner, &chain_id) {
+                self.chain_tracker.discard(&chain_id);
+                return Err(ServerError::LimitExceeded(reason));
+            }
+        }
+        self.tool_sandbox.start(&chain_id, &signal, owner.map(|owner| owner.role.as_str()));
+        
+        // Send signal
+        self.send_signal(signal.clone()).await
+            .map_err(|e| ServerError::RoutingError(e.to_string()))?;
+            
+        // Broadcast event
+        self.events.send(WsMessage::SignalUpdate {
+            signal_id: signal_id.clone(),
+            neuron_id: signal.to_neuron.clone(),
+            status: "submitted".to_string(),
+        });
+        
+        Ok(signal_id)
+    }
+}
+
+#[async_trait]
+impl GoalExecutor for SignalSubmitter {
+    async fn submit(&self, owner: Option<&ChainOwner>, signal: NeuronSignal) -> ServerResult<String> {
+        self.submit_signal_as(owner, signal).await
+    }
 }
 
 impl HAL9Server {
     /// Create a new server instance
     pub fn new(config: ServerConfig) -> Self {
-        let (event_tx, _) = broadcast::channel(1000);
+        let events = Arc::new(EventLog::default());
+        let (worker_events, _) = broadcast::channel(100);
+        
+        metadata_schema::global().write().set_mode(config.metadata_validation);
+        
+        // Seeded randomness and fault injection when simulating
+        let simulation = Simulation::new(config.simulation.clone());
+        let chaos = Arc::new(ChaosEngine::new(simulation.rng("chaos")));
+        let mock_scenarios = Arc::new(MockScenarios::new(&config.claude));
         
         // Create metrics first
         let metrics = Arc::new(Metrics::new());
         
+        // Outbound webhooks
+        let webhooks = Arc::new(WebhookManager::new(config.webhooks.clone()));
+        
         // Create cost tracker with configuration
         let mut cost_tracker = CostTracker::new(config.claude.cost_controls.clone());
         cost_tracker.set_metrics(metrics.clone());
+        let alert_webhooks = webhooks.clone();
+        cost_tracker.set_alert_callback(move |msg| alert_webhooks.emit_budget_threshold(&msg));
         let cost_tracker = Arc::new(cost_tracker);
         
+        // Provider model lifecycle and replacements of retired models
+        let models = Arc::new(ModelRegistry::new(&config.models));
+        
+        // Chain tracking, per-user limits and fair slot scheduling
+        let chain_tracker = Arc::new(ChainTracker::new());
+        let chain_limiter = Arc::new(ChainLimiter::new(config.limits.clone(), chain_tracker.clone()));
+        let scheduler = Arc::new(FairScheduler::new(config.limits.max_concurrent_signals));
+        
+        // Load signals for replica autoscaling and draining on termination
+        let autoscaler = Arc::new(Autoscaler::new(
+            config.autoscaling.clone(),
+            Arc::new(PendingSignals::new()),
+            scheduler.clone(),
+            chain_tracker.clone(),
+        ));
+        metrics.set_autoscaler(autoscaler.clone());
+        
+        // Latency SLOs, fed by layer latencies and finished chains
+        let slo = Arc::new(SloTracker::new(
+            config.monitoring.slos.clone(),
+            config.monitoring.slo_alerts.clone(),
+        ));
+        metrics.set_slo_tracker(slo.clone());
+        
+        // Per-neuron load, used for balancing across clones when scaling is on
+        let load_tracker = Arc::new(LoadTracker::new(
+            config.scaling.neuron_concurrency,
+            Duration::from_millis(config.scaling.latency_window_ms),
+        ));
+        
+        // Layers paused before a restart stay paused
+        let layer_gate = Arc::new(LayerGate::load(config.layer_pause.clone()));
+        metrics.set_layer_gate(layer_gate.clone());
+        let degraded = Arc::new(DegradedMode::new(config.degraded.clone()));
+        let report_privacy = Arc::new(ReportPrivacy::new(config.report_privacy.clone()));
+        let shadow = Arc::new(ShadowMirror::new(config.shadow.clone(), simulation.rng("shadow.sample")));
+        let profiler = Arc::new(Profiler::new(config.profiling.clone()));
+        
+        // Maintenance windows may start at boot
+        let read_only = Arc::new(ReadOnlyGate::new(config.read_only.clone()));
+
+        metrics.set_read_only(read_only.clone());
+        
+        // Signal flow history feeding emergence detection and, through the
+        // traffic it records, layer compression metrics
+        let traffic = Arc::new(LayerTraffic::new(config.compression.clone()));
+        metrics.set_layer_traffic(traffic.clone());
+        let signal_flow = Arc::new(SignalFlowHistory::new(SIGNAL_FLOW_CAPACITY).with_traffic(traffic));
+        let mut detector = SignalFlowDetectorConfig::default();
+        if let Some(seed) = simulation.seed() {
+            detector.seed = seed;
+        }
+        let mut intelligence = DefaultIntelligenceCoordinator::with_signal_flow(
+            signal_flow.clone(),
+            detector,
+        );
+        
+        // Creative problem solving prompts Claude from L9 down to L3
+        let creativity = LayeredCreativityConfig::default();
+        let instances: Result<_> = creativity.levels().into_iter()
+            .map(|level| {
+                let layer = format!("L{}", level);
+                let claude = create_claude_instance(&config.claude, &cost_tracker, &models, &layer, simulation.rng(&format!("creativity.{}", layer)))?;
+                Ok((level, claude))
+            })
+            .collect();
+        match instances {
+            Ok(instances) => intelligence.set_creativity_engine(Box::new(LayeredCreativityEngine::new(
+                Arc::new(ClaudeLayerPrompter::new(instances)),
+                creativity,
+                StrategyLibrary::with_defaults(),
+            ))),
+            Err(e) => warn!("Layered creativity unavailable, using built-in idea generation: {}", e),
+        }
+        
+        // Dependency probes; memory and database probes join once those exist
+        let registry = Arc::new(NeuronRegistry::new());
+        registry.warm_pool().set_config(&config.warm_pool);
+        let health = Arc::new(HealthChecker::new(config.health.clone()));
+        health.register(Arc::new(NeuronsProbe::new(registry.clone())));
+        health.register(Arc::new(ClaudeProbe::new(&config.claude, &config.health, metrics.clone())));
+        health.register(Arc::new(SystemMemoryProbe));
+        health.register(Arc::new(LayerPauseProbe::new(layer_gate.clone())));
+        health.register(Arc::new(DegradedProbe::new(degraded.clone())));
+        health.register(Arc::new(ReadOnlyProbe::new(read_only.clone())));
+        let database_dirs = DiskProbe::database_dirs(&config);
+        if !database_dirs.is_empty() {
+            health.register(Arc::new(DiskProbe::new(database_dirs, &config.health)));
+        }
+        if let Ok(redis_url) = std::env::var("REDIS_URL") {
+            health.register(Arc::new(RedisProbe::new(redis_url)));
+        }
+        
+        // Finished chains and dead letters age out; the signal log and
+        // databases join once opened
+        let retention = Arc::new(RetentionJanitor::new(
+            config.retention.clone(),
+            chain_tracker.clone(),
+            registry.dead_letters().clone(),
+        ));
+        
+        // Snapshots of past state, archived along with payloads
+        let time_travel = Arc::new(TimeTravel::new(chain_tracker.clone(), &config.retention.archive_dir));
+        retention.set_time_travel(time_travel.clone());
+        
+        // Branch and node limits of chains
+        let fan_out = Arc::new(FanOutPolicy::new(config.fan_out.clone()));
+        
+        // Tool call limits of chains, shared by all their branches
+        let tool_sandbox = Arc::new(ToolSandbox::new(config.tool_sandbox.clone(), chain_tracker.clone()));
+        
+        // Goals submit their tasks through the same path as API signals
+        let router = Arc::new(RwLock::new(None));
+        let distributed_router = Arc::new(RwLock::new(None));
+        let submitter = SignalSubmitter {
+            metrics: metrics.clone(),
+            cost_tracker: cost_tracker.clone(),
+            chain_tracker: chain_tracker.clone(),
+            chain_limiter: chain_limiter.clone(),
+            autoscaler: autoscaler.clone(),
+            fan_out: fan_out.clone(),
+            tool_sandbox: tool_sandbox.clone(),
+            router: router.clone(),
+            distributed_router: distributed_router.clone(),
+            events: events.clone(),
+        };
+        let goals = Arc::new(GoalManager::new(
+            config.goals.clone(),
+            &config.neurons,
+            models.pricing(&config.claude.model),
+            chain_tracker.clone(),
+            Arc::new(submitter.clone()),
+        ));
+        
+        // Topology patches start from the configured neurons
+        let topology = TopologyEditor::new(config.neurons.clone(), config.validation.clone(), PATCH_TOKEN_TTL);
+        
+        // Provider call timeouts, learned across every neuron's calls
+        let timeouts = Arc::new(TimeoutPolicy::new(config.timeouts.clone()));
+        let prompt_accounting = Arc::new(PromptAccounting::new(
+            config.prompt_composition.clone(),
+            models.clone(),
+            &config.claude.model,
+        ));
+        let mut approvals = ApprovalGates::new(config.approvals.clone(), chain_tracker.clone());
+        approvals.set_webhooks(webhooks.clone());
+        let approvals = Arc::new(approvals);
+        
         Self {
+            config_layers: parking_lot::RwLock::new(LayeredConfig::from_config(config.clone())),
             config,
-            registry: Arc::new(NeuronRegistry::new()),
+            registry,
             routing_table: Arc::new(RoutingTable::new()),
-            router: RwLock::new(None),
-            distributed_router: RwLock::new(None),
+            router,
+            distributed_router,
             transport: RwLock::new(None),
             discovery: RwLock::new(None),
             metrics,
             cost_tracker,
-            event_tx,
+            chain_tracker,
+            chain_limiter,
+            scheduler,
+            webhooks,
+            local_models: Arc::new(LocalModels::new()),
+            codegen_jobs: Arc::new(CodegenJobs::default()),
+            health,
+            memory: RwLock::new(None),
+            memory_tiering: RwLock::new(None),
+            memory_summarization: RwLock::new(None),
+            receipts: RwLock::new(None),
+            keyring: RwLock::new(None),
+            ingestion: RwLock::new(None),
+            quality: RwLock::new(None),
+            templates: RwLock::new(None),
+            sessions: RwLock::new(None),
+            billing_export: RwLock::new(None),
+            layer_gate,
+            degraded,
+            report_privacy,
+            shadow,
+            profiler,
+            read_only,
+            retention,
+            time_travel,
+            timeouts,
+            prompt_accounting,
+            approvals,
+            models,
+            fan_out,
+            tool_sandbox,
+            autoscaler,
+            slo,
+            load_tracker,
+            self_organizer: RwLock::new(None),
+            neuron_factory: parking_lot::RwLock::new(None),
+            topology,
+            topology_apply: tokio::sync::Mutex::new(()),
+            signal_flow,
+            intelligence,
+            goals,
+            submitter,
+            simulation,
+            chaos,
+            mock_scenarios,
+            events,
+            worker_events,
             start_time: RwLock::new(None),
             user_manager: None,
             jwt_manager: None,
             api_key_manager: None,
+            org_usage: None,
         }
     }
     
+    /// Create a server from layered config, keeping track of where each
+    /// value came from
+    pub fn with_config_layers(layers: LayeredConfig) -> Self {
+        let mut server = Self::new(layers.config().clone());
+        *server.config_layers.get_mut() = layers;
+        server
+    }
+    
     /// Initialize authentication if enabled
-    pub async fn initialize_auth(&mut self, pool: sqlx::SqlitePool) -> Result<()> {
+    pub async fn initialize_auth(&mut self, pools: SqlitePools) -> Result<()> {
         if self.config.auth.enabled {
             info!("Initializing authentication system");
             
+            self.health.register(Arc::new(DatabaseProbe::new(pools.reader().clone(), &self.config.health)));
+            self.metrics.register_database_pool("auth", Arc::new(pools.clone()));
+            self.read_only.register_database("auth", &pools).await;
+            self.retention.register_database("auth", &pools);
+            
             // Create managers
-            let user_manager = Arc::new(UserManager::new(pool.clone()));
+            let user_manager = Arc::new(UserManager::new(pools.clone()));
             let jwt_manager = Arc::new(JwtManager::with_durations(
-                self.config.auth.jwt_secret.clone(),
+                self.config.auth.jwt_secret.expose().to_string(),
                 self.config.auth.access_token_duration_minutes,
                 self.config.auth.refresh_token_duration_days,
             ));
-            let api_key_manager = Arc::new(ApiKeyManager::new(pool.clone()));
+            let api_key_manager = Arc::new(ApiKeyManager::new(pools.clone()));
             
             // Initialize tables
             user_manager.initialize().await
@@ -101,6 +569,16 @@ impl HAL9Server {
             self.jwt_manager = Some(jwt_manager);
             self.api_key_manager = Some(api_key_manager);
             
+            let org_usage = Arc::new(OrgUsageStore::new(pools.clone(), self.models.billed_model(&self.config.claude.model)));
+            org_usage.initialize().await
+                .map_err(|e| Error::Other(anyhow::anyhow!("Failed to initialize usage tables: {}", e)))?;
+            self.org_usage = Some(org_usage);
+            
+            self.webhooks.attach_store(pools.clone()).await
+                .map_err(|e| Error::Other(anyhow::anyhow!("Failed to initialize webhook tables: {}", e)))?;
+            self.report_privacy.attach_store(pools.clone()).await
+                .map_err(|e| Error::Other(anyhow::anyhow!("Failed to initialize privacy budget tables: {}", e)))?;
+            
             info!("Authentication system initialized");
         }
         
@@ -111,6 +589,49 @@ impl HAL9Server {
     pub async fn start(&self) -> Result<()> {
         info!("Starting 2HAL9 server: {}", self.config.server_id);
         
+        // Only mock Claude replays deterministically
+        if self.simulation.is_enabled() && self.config.claude.mode != "mock" {
+            return Err(Error::Config(format!(
+                "Simulation mode needs claude.mode \"mock\", got \"{}\"",
+                self.config.claude.mode
+            )));
+        }
+        
+        slo::validate_slos(&self.config.monitoring.slos)?;
+        timeouts::validate(&self.config.timeouts)?;
+        model_registry::validate(&self.config.models)?;
+        fan_out::validate(&self.config.fan_out)?;
+        tool_sandbox::validate(&self.config.tool_sandbox)?;
+        singularity::validate(&self.config.singularity)?;
+        tenant_encryption::validate(&self.config.encryption)?;
+        speculation::validate(&self.config.speculation)?;
+        ingestion::validate(&self.config.ingestion, &self.config.claude.cost_controls.tags)?;
+        quality::validate(&self.config.quality, &self.config.database)?;
+        shadow::validate(&self.config.shadow, &self.config.database)?;
+        templates::validate(&self.config.templates, &self.config.database)?;
+        sessions::validate(&self.config.sessions, &self.config.database)?;
+        billing_export::validate(&self.config.billing_export, &self.config.auth)?;
+        prompt_composition::validate(&self.config.prompt_composition)?;
+        approvals::validate(&self.config.approvals)?;
+        performance::validate(&self.config.profiling)?;
+        time_travel::validate(&self.config.time_travel)?;
+        mock_scenarios::validate(&self.config.claude)?;
+        self.mock_scenarios.activate_configured()?;
+        
+        // Nothing may touch a database whose schema this release does not know
+        if let Some(pool) = database_migrations::connect(&self.config.database).await? {
+            let result = database_migrations::prepare(&pool, &self.config.database).await;
+            pool.close().await;
+            result?;
+        }
+        
+        // Staging chains started by shadows, recorded next to production's
+        if self.config.shadow.enabled {
+            if let Some(pool) = database_migrations::connect(&self.config.database).await? {
+                self.shadow.attach_store(ShadowStore::new(pool));
+            }
+        }
+        
         // Record start time
         *self.start_time.write().await = Some(Instant::now());
         
@@ -124,11 +645,38 @@ impl HAL9Server {
         };
         registry_ref.set_metrics(self.metrics.clone());
         
+        // Organization data keys, before anything that stores payloads
+        let keyring = tenant_encryption::open_keyring(&self.config.encryption).await?;
+        *self.keyring.write().await = keyring.clone();
+        let mut sealed_memory = None;
+        let mut memory_meter = None;
+        
         // Initialize memory system if enabled
-        let memory_store = if self.config.memory.enabled {
+        let memory = if self.config.memory.enabled {
             info!("Initializing memory system");
-            let memory_manager = crate::memory_manager::MemoryManager::new(&self.config.memory).await?;
+            let sealing = keyring.clone().map(|keyring| {
+                let search = tenant_encryption::sealed_search(&self.config.encryption, self.config.memory.embedding_dimension);
+                (keyring, search)
+            });
+            let memory_manager = crate::memory_manager::MemoryManager::new(&self.config.memory, sealing).await?;
             let store = memory_manager.get_store();
+            sealed_memory = keyring.as_ref().map(|_| store.clone());
+            memory_meter = Some(memory_manager.sqlite_store());
+            self.metrics.register_database_pool("memory", Arc::new(memory_manager.pools()));
+            self.read_only.register_database("memory", &memory_manager.pools()).await;
+            self.retention.register_database("memory", &memory_manager.pools());
+            self.health.register(Arc::new(MemoryBackendProbe::new(store.clone())));
+            
+            // Idle entries moved to the cold tier, rate limited
+            let sqlite = memory_manager.sqlite_store();
+            let tiering = Arc::new(TieringJob::new(self.config.memory.tiering.clone(), sqlite.clone()));
+            if let Some(cold) = sqlite.cold_tier() {
+                self.metrics.set_cold_tier(cold.clone());
+            }
+            if self.config.memory.tiering.enabled {
+                tokio::spawn(memory_tiering::tiering_task(tiering.clone(), self.read_only.clone()));
+            }
+            *self.memory_tiering.write().await = Some(tiering);
             
             // Start cleanup task if enabled
             if self.config.memory.cleanup.retention_days > 0 {
@@ -137,39 +685,136 @@ impl HAL9Server {
                 tokio::spawn(crate::memory_manager::cleanup_task(manager, cleanup_config));
             }
             
-            Some(store)
+            // Namespace policies and quotas, with periodic expiry sweeps
+            let mut memory = NamespacedMemory::new(store, self.config.memory.namespaces.clone());
+            
+            // Pending writes to moderated namespaces reviewed by a model
+            let moderation = &self.config.memory.namespaces.moderation;
+            if let Some(MemoryValidatorConfig::Judge { model }) = &moderation.validator {
+                let mut judge_config = self.config.claude.clone();
+                judge_config.model = model.clone();
+                let rng = self.simulation.rng("memory.judge");
+                let judge = create_claude_instance(&judge_config, &self.cost_tracker, &self.models, "L4", rng)?;
+                memory.set_reviewer(Arc::new(JudgeReviewer::new(judge)));
+            }
+            
+            // Plugin-backed namespaces are attached by a plugin manager,
+            // which this server does not run
+            for (namespace, backend) in &self.config.memory.namespaces.backends {
+                warn!(
+                    "Memory namespace {} is stored by plugin {}, which is not loaded; it stays read-only",
+                    namespace, backend.plugin
+                );
+            }
+            let memory = Arc::new(memory);
+            tokio::spawn(hal9_core::memory::namespace::sweep_task(memory.clone()));
+            if moderation.validator.is_some() {
+                tokio::spawn(hal9_core::memory::namespace::review_task(memory.clone()));
+            }
+            
+            // Namespaces near their quota summarized by a cheap model
+            let summarization = &self.config.memory.summarization;
+            let mut summary_config = self.config.claude.clone();
+            summary_config.model = summarization.model.clone();
+            let rng = self.simulation.rng("memory.summarizer");
+            let summarizer = create_claude_instance(&summary_config, &self.cost_tracker, &self.models, "L4", rng)?;
+            let summarization_job = Arc::new(SummarizationJob::new(
+                summarization.clone(),
+                memory.clone(),
+                sqlite,
+                Arc::new(ModelSummarizer::new(summarizer)),
+                self.config.memory.embedding_dimension,
+            ));
+            if summarization.enabled {
+                tokio::spawn(memory_summarization::summarization_task(summarization_job.clone(), self.read_only.clone()));
+            }
+            *self.memory_summarization.write().await = Some(summarization_job);
+            
+            *self.memory.write().await = Some(memory.clone());
+            Some(memory)
         } else {
             None
         };
         
-        // Spawn neurons
-        for neuron_config in &self.config.neurons {
-            let claude = self.create_claude_instance(&neuron_config.layer).await?;
-            let mut neuron = ManagedNeuron::new(neuron_config.clone(), claude)?;
-            
-            // Set memory store if available
-            if let Some(store) = &memory_store {
-                neuron.set_memory_store(store.clone());
+        // Signal log pruned by the retention janitor
+        let mut sealed_signals = None;
+        if let Some(path) = &self.config.retention.signal_log_path {
+            let url = format!("sqlite:{}?mode=rwc", path);
+            let mut signal_log = RuntimeDatabase::connect(&url, DatabaseType::Sqlite, SqliteTuning::default())
+                .await
+                .map_err(|e| Error::Storage(format!("Failed to open signal log {}: {}", path, e)))?;
+            if let Some(keyring) = &keyring {
+                signal_log = signal_log.with_keyring(keyring.clone());
             }
-            
-            // Enable backward propagation if configured
-            if self.config.backward_propagation.enabled {
-                let base_prompt = neuron_config.system_prompt.clone()
-                    .unwrap_or_else(|| format!("You are neuron {} on layer {}", neuron_config.id, neuron_config.layer));
-                neuron.enable_backward_propagation(self.config.backward_propagation.clone(), base_prompt);
+            let signal_log = Arc::new(signal_log);
+            self.metrics.register_database_pool(retention::SIGNAL_LOG, signal_log.clone());
+            self.retention.set_signal_log(signal_log.clone());
+            if keyring.is_some() {
+                sealed_signals = Some(signal_log);
             }
-            
+        }
+        if self.config.retention.enabled {
+            tokio::spawn(retention::janitor_task(self.retention.clone()));
+        }
+        
+        // Move values off retired organization keys
+        if keyring.is_some() {
+            tokio::spawn(tenant_encryption::sweep_task(
+                sealed_memory,
+                sealed_signals,
+                Duration::from_secs(self.config.encryption.sweep_interval_secs),
+            ));
+        }
+        
+        // Receipt signing key and anchor
+        let receipts = ReceiptManager::from_config(&self.config.receipts).await?;
+        *self.receipts.write().await = Some(Arc::new(receipts));
+        
+        // Worker crashes and restarts surface as neuron state changes
+        tokio::spawn(forward_worker_events(self.worker_events.subscribe(), self.events.clone()));
+        
+        // Warn about deprecated models and retired ones without a replacement;
+        // mock neurons never call the provider
+        if self.config.claude.mode != "mock" {
+            tokio::spawn(model_registry::check_task(
+                self.models.clone(),
+                model_registry::configured_models(&self.config),
+                Duration::from_secs(self.config.models.check_interval_secs),
+            ));
+        }
+        
+        // Spawn neurons
+        let factory = self.neuron_factory(memory);
+        for neuron_config in &self.config.neurons {
+            let neuron = factory(neuron_config.clone())?;
             self.registry.register(neuron).await?;
         }
+        *self.neuron_factory.write() = Some(factory.clone());
         
         // Update metrics
         self.metrics.set_active_neurons(self.config.neurons.len() as u64);
         
+        // Keep warm pools of slow-starting neuron types filled
+        tokio::spawn(crate::warm_pool::refresh_task(self.registry.warm_pool().clone()));
+        
+        // Find neuron changes for clients streaming them
+        tokio::spawn(crate::neuron_changes::refresh_task(self.registry.clone()));
+        
+        // Size the log index behind the logs API
+        if let Some(index) = crate::logging::log_index() {
+            index.configure(&self.config.monitoring.log_index);
+        }
+        
         // Start periodic metrics reporting if enabled
         if self.config.monitoring.enabled {
             self.start_metrics_reporter().await;
         }
         
+        // Snapshot state for debug views of past instants
+        if self.config.time_travel.enabled {
+            self.start_snapshot_recorder();
+        }
+        
         // Initialize network if enabled
         if self.config.network.enabled {
             info!("Initializing distributed networking");
@@ -181,11 +826,24 @@ impl HAL9Server {
             let transport_config = crate::network::tcp_transport::TransportConfig {
                 bind_address,
                 max_connections: self.config.network.max_connections,
+                tls_enabled: self.config.network.tls_enabled,
+                tls_cert_path: self.config.network.tls_cert.clone(),
+                tls_key_path: self.config.network.tls_key.clone(),
+                tls_ca_path: self.config.network.tls_ca.clone(),
+                tls_require_client_cert: self.config.network.tls_require_client_cert,
+                peer_allowed_layers: self.config.network.peer_allowed_layers.clone(),
+                compression: self.config.network.compression_enabled.then_some(crate::network::protocol::Compression {
+                    level: self.config.network.compression_level,
+                    threshold: self.config.network.compression_min_bytes,
+                }),
+                batch_window: Duration::from_millis(self.config.network.batch_window_ms),
+                batch_max_signals: self.config.network.batch_max_signals,
                 ..Default::default()
             };
             
             let mut transport = TcpTransport::new(transport_config, self.config.server_id.clone());
             transport.set_metrics(self.metrics.clone());
+            transport.set_chaos(self.chaos.clone());
             transport.start().await?;
             let transport = Arc::new(transport);
             *self.transport.write().await = Some(transport.clone());
@@ -224,12 +882,73 @@ impl HAL9Server {
             }
         }
         
+        // Announce finished chains to webhooks
+        self.start_chain_notifier();
+        if let Some(store) = &self.org_usage {
+            tokio::spawn(org_usage::recorder_task(
+                store.clone(),
+                self.chain_tracker.clone(),
+                self.chain_tracker.subscribe_started(),
+                self.chain_tracker.subscribe(),
+                self.webhooks.subscribe(),
+                self.registry.dead_letters().subscribe(),
+            ));
+        }
+        tokio::spawn(cost_tags::recorder_task(
+            self.cost_tracker.clone(),
+            self.chain_tracker.clone(),
+            self.chain_tracker.subscribe(),
+            self.models.billed_model(&self.config.claude.model),
+        ));
+        if !self.slo.is_empty() {
+            let check_interval = Duration::from_secs(self.config.monitoring.slo_alerts.check_interval_secs);
+            tokio::spawn(slo::chain_recorder_task(self.slo.clone(), self.chain_tracker.subscribe()));
+            tokio::spawn(slo::alert_task(self.slo.clone(), self.webhooks.clone(), check_interval));
+        }
+        if let Some(bridge) = SingularityBridge::new(&self.config.singularity, &self.signal_flow)? {
+            tokio::spawn(singularity::sync_task(
+                bridge,
+                self.registry.clone(),
+                self.signal_flow.clone(),
+                self.events.clone(),
+                self.webhooks.clone(),
+                Duration::from_secs(self.config.singularity.interval_secs),
+            ));
+        }
+        self.goals.start();
+        
         // Start signal router
+        let speculation = Speculator::new(&self.config.speculation).map(|mut speculator| {
+            speculator.set_metrics(self.metrics.clone());
+            Arc::new(speculator)
+        });
         let mut router = SignalRouter::new(
             self.registry.clone(),
             self.routing_table.clone(),
         );
+        router.set_chain_tracker(self.chain_tracker.clone());
+        router.set_scheduler(self.scheduler.clone());
+        router.set_signal_flow(self.signal_flow.clone());
+        router.set_layer_gate(self.layer_gate.clone());
+        router.set_degraded(self.degraded.clone());
+        router.set_approvals(self.approvals.clone());
+        router.set_poll_monitor(self.profiler.polls());
+        router.set_pending_signals(self.autoscaler.pending());
+        if self.config.scaling.enabled {
+            router.set_load_tracker(self.load_tracker.clone());
+        }
+        if let Some(speculation) = &speculation {
+            router.set_speculation(speculation.clone());
+        }
         router.start().await?;
+        self.layer_gate.start();
+        self.degraded.start(self.registry.clone());
+        self.approvals.start();
+        
+        // Clone neurons of overloaded layers
+        if self.config.scaling.enabled {
+            self.start_self_organizer(factory).await;
+        }
         
         // Store the router for local use
         *self.router.write().await = Some(router);
@@ -259,6 +978,20 @@ impl HAL9Server {
                         self.registry.clone(),
                         self.routing_table.clone(),
                     );
+                    distributed_local_router.set_chain_tracker(self.chain_tracker.clone());
+                    distributed_local_router.set_scheduler(self.scheduler.clone());
+                    distributed_local_router.set_signal_flow(self.signal_flow.clone());
+                    distributed_local_router.set_layer_gate(self.layer_gate.clone());
+                    distributed_local_router.set_degraded(self.degraded.clone());
+                    distributed_local_router.set_approvals(self.approvals.clone());
+                    distributed_local_router.set_poll_monitor(self.profiler.polls());
+                    distributed_local_router.set_pending_signals(self.autoscaler.pending());
+                    if self.config.scaling.enabled {
+                        distributed_local_router.set_load_tracker(self.load_tracker.clone());
+                    }
+                    if let Some(speculation) = &speculation {
+                        distributed_local_router.set_speculation(speculation.clone());
+                    }
                     distributed_local_router.start().await?;
                     
                     // Create distributed router using the new started router
@@ -284,67 +1017,206 @@ impl HAL9Server {
             }
         }
         
+        // Take chains in from Kafka and webhooks once they can be routed
+        let ingestion = Ingestion::open(
+            &self.config,
+            self.submitter.clone(),
+            self.cost_tracker.clone(),
+            self.read_only.clone(),
+        )
+        .await?;
+        if let Some(ingestion) = ingestion.map(Arc::new) {
+            self.metrics.register_database_pool("ingest_quarantine", Arc::new(ingestion.quarantine_pools().clone()));
+            self.metrics.set_ingestion(ingestion.clone());
+            ingestion.start();
+            *self.ingestion.write().await = Some(ingestion);
+        }
+        
+        // Quality benchmarks, scored again when started with a configuration
+        // they were not run against
+        if self.config.quality.enabled {
+            let mut judge_config = self.config.claude.clone();
+            if let Some(model) = &self.config.quality.judge_model {
+                judge_config.model = model.clone();
+            }
+            let rng = self.simulation.rng("quality.judge");
+            let judge = create_claude_instance(&judge_config, &self.cost_tracker, &self.models, "judge", rng)?;
+            if let Some(harness) = QualityHarness::open(&self.config, judge).await?.map(Arc::new) {
+                if harness.config().run_on_change && harness.needs_run(&self.config.neurons).await? {
+                    self.spawn_quality_run(harness.clone(), RunTrigger::Deploy);
+                }
+                *self.quality.write().await = Some(harness);
+            }
+        }
+        
+        // Chain templates, kept in the server database
+        if let Some(library) = TemplateLibrary::open(&self.config).await? {
+            *self.templates.write().await = Some(Arc::new(library));
+        }
+        
+        // Sessions, whose summaries a cheap model keeps after each turn
+        if self.config.sessions.enabled {
+            let mut summary_config = self.config.claude.clone();
+            summary_config.model = self.config.sessions.summary_model.clone();
+            let rng = self.simulation.rng("sessions.summary");
+            let summarizer = create_claude_instance(&summary_config, &self.cost_tracker, &self.models, "L4", rng)?;
+            if let Some(manager) = SessionManager::open(&self.config, summarizer).await?.map(Arc::new) {
+                self.start_session_recorder(manager.clone());
+                *self.sessions.write().await = Some(manager);
+            }
+        }
+        
+        // Usage files for billing, from the rows organization usage records
+        if let (true, Some(usage)) = (self.config.billing_export.enabled, &self.org_usage) {
+            let config = self.config.billing_export.clone();
+            let destination = Destination::from_config(&config.destination)?;
+            let mut exporter = BillingExporter::new(config, usage.pools().clone(), destination);
+            if let Some(library) = self.templates.read().await.clone() {
+                exporter = exporter.with_artifacts(library);
+            }
+            if let Some(store) = memory_meter {
+                exporter = exporter.with_memory(store);
+            }
+            exporter.initialize().await?;
+            let exporter = Arc::new(exporter);
+            tokio::spawn(billing_export::export_task(exporter.clone()));
+            *self.billing_export.write().await = Some(exporter);
+        }
+        
         info!("Server started with {} neurons", self.config.neurons.len());
         Ok(())
     }
     
-    /// Create Claude instance based on configuration
-    async fn create_claude_instance(&self, layer: &str) -> Result<Box<dyn ClaudeInterface>> {
-        match self.config.claude.mode.as_str() {
-            "mock" => {
-                info!("Creating mock Claude for layer {}", layer);
-                Ok(Box::new(MockClaude::new(layer, &self.config.claude)))
+    /// Factory building neurons with this server's provider, memory and
+    /// learning setup; used at startup and for clones
+    fn neuron_factory(&self, memory: Option<Arc<NamespacedMemory>>) -> NeuronFactory {
+        let claude_config = self.config.claude.clone();
+        let backward_propagation = self.config.backward_propagation.clone();
+        let cost_tracker = self.cost_tracker.clone();
+        let validation = self.config.validation.clone();
+        let recovery = self.config.recovery.clone();
+        let local_model_configs = self.config.local_models.clone();
+        let local_models = self.local_models.clone();
+        let output_format = self.config.output_format.clone();
+        let metrics = self.metrics.clone();
+        let simulation = self.simulation.clone();
+        let server_config = self.config.clone();
+        let dead_letters = self.registry.dead_letters().clone();
+        let worker_events = self.worker_events.clone();
+        let read_only = self.read_only.clone();
+        let timeouts = self.timeouts.clone();
+        let prompt_accounting = self.prompt_accounting.clone();
+        let fan_out = self.fan_out.clone();
+        let tool_sandbox = self.tool_sandbox.clone();
+        let models = self.models.clone();
+        let chaos = self.chaos.clone();
+        let mock_scenarios = self.mock_scenarios.clone();
+        
+        Arc::new(move |neuron_config: NeuronConfig| {
+            let rng = simulation.rng(&format!("claude.{}", neuron_config.id));
+            // Layers served by a local model share its batches
+            let claude: Box<dyn ClaudeInterface> = match local_model_configs.get(&neuron_config.layer) {
+                Some(local) if claude_config.mode != "mock" => Box::new(LocalModelClaude::new(
+                    local_models.batcher(local, &metrics),
+                    &neuron_config.layer,
+                )),
+                _ => create_claude_instance(&claude_config, &cost_tracker, &models, &neuron_config.layer, rng)?,
+            };
+            let claude = mock_scenarios.wrap_claude(claude, &neuron_config.id);
+            let claude = simulation.inject_faults(claude, &neuron_config.id, &neuron_config.layer);
+            let claude = chaos.wrap_claude(claude, &neuron_config.id, &neuron_config.layer);
+            let mut neuron = ManagedNeuron::new(neuron_config.clone(), claude)?;
+            neuron.set_metrics(metrics.clone());
+            neuron.set_timeouts(timeouts.clone());
+            neuron.set_prompt_accounting(prompt_accounting.clone());
+            neuron.set_fan_out(fan_out.clone());
+            neuron.set_tool_sandbox(tool_sandbox.clone());
+            neuron.set_chaos(chaos.clone());
+            
+            // Set memory if available
+            if let Some(memory) = &memory {
+                neuron.set_memory(memory.clone());
+                neuron.set_read_only(read_only.clone());
             }
-            "api" => {
-                info!("Creating Claude API client for layer {}", layer);
-                let api_key = self.config.claude.api_key.clone()
-                    .or_else(|| std::env::var("ANTHROPIC_API_KEY").ok())
-                    .ok_or_else(|| Error::Config("Claude API key not found".to_string()))?;
-                    
-                let mut api_client = ClaudeAPIClient::new(
-                    api_key,
-                    self.config.claude.model.clone(),
-                    layer,
-                    self.config.claude.temperature,
-                    self.config.claude.max_tokens,
-                );
-                
-                // Set cost tracker
-                api_client.set_cost_tracker(self.cost_tracker.clone());
-                
-                // If fallback is enabled, wrap in FallbackClaude
-                if self.config.claude.fallback_to_mock {
-                    info!("Enabling fallback to mock mode for layer {}", layer);
-                    let mock_client = Box::new(MockClaude::new(layer, &self.config.claude));
-                    Ok(Box::new(FallbackClaude::new(Box::new(api_client), mock_client)))
-                } else {
-                    Ok(Box::new(api_client))
-                }
+            
+            // Validate responses before they are forwarded
+            if let Some(pipeline) = ValidationPipeline::for_neuron(&validation, &neuron_config)? {
+                neuron.set_validation(pipeline);
+            }
+            
+            // Terminal neurons have outputs that miss their requested format
+            // rewritten by a cheaper model
+            if neuron.is_terminal() {
+                let mut reformat_config = claude_config.clone();
+                reformat_config.model = output_format.reformat_model.clone();
+                let rng = simulation.rng(&format!("reformat.{}", neuron_config.id));
+                let reformatter = create_claude_instance(&reformat_config, &cost_tracker, &models, &neuron_config.layer, rng)?;
+                neuron.set_reformatter(reformatter, output_format.max_reformat_retries);
             }
-            "hybrid" | "auto" => {
-                info!("Creating hybrid Claude for layer {} (mode: {})", layer, self.config.claude.mode);
-                Ok(Box::new(HybridClaude::new(
-                    layer,
-                    &self.config.claude,
-                    self.cost_tracker.clone(),
-                )?))
+            
+            // Failed signals run through the configured recovery playbook
+            let recovery_claude = |model: &str| {
+                let mut config = claude_config.clone();
+                config.model = model.to_string();
+                let rng = simulation.rng(&format!("recovery.{}.{}", neuron_config.id, model));
+                create_claude_instance(&config, &cost_tracker, &models, &neuron_config.layer, rng)
+            };
+            if let Some(playbook) = RecoveryPlaybook::for_neuron(&recovery, &neuron_config, &recovery_claude)? {
+                neuron.set_recovery(playbook);
             }
-            mode => Err(Error::Config(format!("Unknown Claude mode: {}", mode))),
-        }
+            
+            // Enable backward propagation if configured
+            if backward_propagation.enabled {
+                let base_prompt = neuron_config.system_prompt.clone()
+                    .unwrap_or_else(|| format!("You are neuron {} on layer {}", neuron_config.id, neuron_config.layer));
+                neuron.enable_backward_propagation(backward_propagation.clone(), base_prompt);
+            }
+            
+            // Isolated neurons process signals in a supervised worker process
+            if IsolationMode::for_neuron(&neuron_config)? == IsolationMode::Process {
+                neuron.set_worker(Arc::new(NeuronWorker::new(
+                    neuron_config.clone(),
+                    server_config.clone(),
+                    dead_letters.clone(),
+                    cost_tracker.clone(),
+                    worker_events.clone(),
+                )));
+            }
+            
+            Ok(neuron)
+        })
+    }
+    
+    /// Build a neuron as the server would, without memory, for a worker
+    /// process to run in place of an isolated neuron
+    pub fn worker_neuron(&self, neuron_config: NeuronConfig) -> Result<ManagedNeuron> {
+        (self.neuron_factory(None))(neuron_config)
+    }
+    
+    /// Start the load-based self-organizer
+    async fn start_self_organizer(&self, factory: NeuronFactory) {
+        let mut organizer = LoadSelfOrganizer::new(
+            self.config.scaling.clone(),
+            self.registry.clone(),
+            self.routing_table.clone(),
+            self.load_tracker.clone(),
+            factory,
+        );
+        let cost_tracker = self.cost_tracker.clone();
+        organizer.set_budget_probe(Arc::new(move || {
+            let cost_tracker = cost_tracker.clone();
+            Box::pin(async move { cost_tracker.budget_utilization().await })
+        }));
+        
+        let organizer = Arc::new(organizer);
+        tokio::spawn(scaling_task(organizer.clone()));
+        *self.self_organizer.write().await = Some(organizer);
+        info!("Load-based self-organization enabled");
     }
     
     /// Send a signal to the network
     pub async fn send_signal(&self, signal: NeuronSignal) -> Result<()> {
-        self.metrics.record_signal_sent();
-        
-        // Use distributed router if available
-        if let Some(distributed_router) = self.distributed_router.read().await.as_ref() {
-            distributed_router.route_signal(signal).await
-        } else if let Some(router) = self.router.read().await.as_ref() {
-            router.send_signal(signal).await
-        } else {
-            Err(Error::InvalidState("Server not started".to_string()))
-        }
+        self.submitter.send_signal(signal).await
     }
     
     /// Get server status
@@ -390,6 +1262,13 @@ impl HAL9Server {
         // Shutdown all neurons
         self.registry.shutdown_all().await?;
         
+        // Keep what was recorded of the run
+        if let Some(recorder) = self.mock_scenarios.recorder() {
+            if let Err(e) = recorder.save() {
+                warn!("Failed to save the mock scenario recording: {}", e);
+            }
+        }
+        
         info!("Server shutdown complete");
         Ok(())
     }
@@ -404,6 +1283,65 @@ impl HAL9Server {
         self.registry.clone()
     }
     
+    /// Dependency health checks behind the health endpoints
+    pub fn health(&self) -> Arc<HealthChecker> {
+        self.health.clone()
+    }
+    
+    /// Load signals, replica recommendations and drain state
+    pub fn autoscaler(&self) -> Arc<Autoscaler> {
+        self.autoscaler.clone()
+    }
+    
+    /// Simulation seed and fault injector
+    pub fn simulation(&self) -> &Simulation {
+        &self.simulation
+    }
+    
+    /// Start injecting a fault, see [`crate::chaos`]
+    pub fn inject_chaos(&self, request: ChaosRequest, actor: Option<&str>) -> ServerResult<ChaosInjection> {
+        self.chaos.inject(request, actor)
+    }
+    
+    /// Stop an injection before it runs out
+    pub fn stop_chaos(&self, id: Uuid, actor: Option<&str>) -> ServerResult<ChaosInjection> {
+        self.chaos.stop(id, actor)
+    }
+    
+    /// Stop every injection, returning how many were active
+    pub fn clear_chaos(&self, actor: Option<&str>) -> usize {
+        self.chaos.clear(actor)
+    }
+    
+    /// Active injections and whether this build accepts them
+    pub fn chaos_status(&self) -> ChaosStatus {
+        self.chaos.status()
+    }
+    
+    /// Engine applying injected faults
+    pub fn chaos(&self) -> Arc<ChaosEngine> {
+        self.chaos.clone()
+    }
+    
+    /// Scenario files, the one replayed and the recording, see
+    /// [`crate::mock_scenarios`]
+    pub fn mock_scenarios(&self) -> ScenarioStatus {
+        self.mock_scenarios.status()
+    }
+    
+    /// Replay the scenario `name` from its start, or stop replaying with `None`
+    pub fn select_mock_scenario(&self, name: Option<&str>, strict: Option<bool>) -> ServerResult<ScenarioStatus> {
+        self.mock_scenarios.select(name, strict)
+    }
+    
+    /// Write the exchanges recorded so far to `claude.scenarios.record_to`
+    pub fn save_mock_recording(&self) -> ServerResult<RecordingSaved> {
+        let recorder = self.mock_scenarios.recorder().ok_or_else(|| {
+            ServerError::InvalidInput("Not recording; set claude.scenarios.record_to".to_string())
+        })?;
+        recorder.save()
+    }
+    
     /// Get server ID
     pub fn server_id(&self) -> &str {
         &self.config.server_id
@@ -424,6 +1362,14 @@ impl HAL9Server {
         }
     }
     
+    /// Re-read the network TLS certificate, key and CA files without a restart
+    pub async fn reload_network_tls(&self) -> ServerResult<()> {
+        match self.transport.read().await.as_ref() {
+            Some(transport) => transport.reload_tls()
+                .map_err(|e| ServerError::ConfigError(e.to_string())),
+            None => Err(ServerError::InvalidInput("Distributed networking is not enabled".to_string())),
+        }
+    }
     
     // API-specific methods
     
@@ -443,39 +1389,928 @@ impl HAL9Server {
             neurons,
             metrics,
             network_status,
+            paused_layers: self.layer_gate.paused(),
+            degraded: self.degraded.status(),
+            read_only: self.read_only.status(),
         })
     }
     
     /// Submit a signal to the network
+    ///
+    /// The submitted signal becomes the root of a new chain; the returned
+    /// signal ID is also the chain ID.
     pub async fn submit_signal(&self, signal: NeuronSignal) -> ServerResult<String> {
-        let signal_id = signal.signal_id.to_string();
-        
-        // Send signal
-        self.send_signal(signal.clone()).await
-            .map_err(|e| ServerError::RoutingError(e.to_string()))?;
-            
-        // Broadcast event
-        let _ = self.event_tx.send(WsMessage::SignalUpdate {
-            signal_id: signal_id.clone(),
-            neuron_id: signal.to_neuron.clone(),
-            status: "submitted".to_string(),
-        });
-        
-        Ok(signal_id)
+        self.submit_signal_as(None, signal).await
     }
     
-    /// List all neurons
-    pub async fn list_neurons(&self) -> ServerResult<Vec<NeuronInfo>> {
-        Ok(self.registry.list_all().await)
+    /// Submit a signal on behalf of a user, enforcing their concurrent chain limit.
+    /// Metadata that fails the metadata schema is rejected as invalid input.
+    ///
+    /// A signal naming a session becomes its next turn and carries the
+    /// conversation so far.
+    pub async fn submit_signal_as(&self, owner: Option<&ChainOwner>, mut signal: NeuronSignal) -> ServerResult<String> {
+        signal.metadata.remove(SESSION_CONTEXT_KEY);
+        if signal.metadata.contains_key(SESSION_ID_KEY) {
+            self.sessions().await?.prepare(owner, &mut signal).await?;
+        }
+        self.submitter.submit_signal_as(owner, signal).await
     }
     
-    /// Get specific neuron info
-    pub async fn get_neuron_info(&self, neuron_id: &str) -> ServerResult<NeuronInfo> {
-        self.registry.get_info(neuron_id).await
-            .ok_or_else(|| ServerError::NotFound(format!("Neuron {} not found", neuron_id)))
+    /// Decompose a goal into tasks and submit the first of them
+    pub async fn create_goal(&self, goal: Goal, owner: Option<ChainOwner>) -> ServerResult<GoalStatus> {
+        let plan = self.intelligence.plan_goal(&goal);
+        self.goals.create(goal, plan, owner).await
     }
     
-    /// Get neuron health
+    /// Get a goal's progress
+    pub fn goal_status(&self, goal_id: Uuid) -> ServerResult<GoalStatus> {
+        self.goals.status(goal_id)
+            .ok_or_else(|| ServerError::NotFound(format!("Goal {} not found", goal_id)))
+    }
+    
+    /// Signals processed since `since`, of one direction if given
+    pub fn signal_activity(&self, since: Option<chrono::DateTime<chrono::Utc>>, direction: Option<PropagationType>) -> Vec<SignalActivity> {
+        self.chain_tracker.activity(since, direction)
+    }
+    
+    /// Get the aggregated result of a chain, archived or not
+    pub async fn get_chain_result(&self, chain_id: &str) -> ServerResult<ChainResult> {
+        if let Some(result) = self.chain_tracker.aggregate(chain_id) {
+            return Ok(result);
+        }
+        self.retention.archived_result(chain_id).await?
+            .ok_or_else(|| ServerError::NotFound(format!("Chain {} not found", chain_id)))
+    }
+    
+    /// Get the full record of a chain, reading it back from the archive if
+    /// its payloads were archived
+    pub async fn chain_record(&self, chain_id: &str) -> ServerResult<ChainRecord> {
+        if let Some(record) = self.chain_tracker.get(chain_id) {
+            return Ok(record);
+        }
+        self.retention.archived_record(chain_id).await?
+            .ok_or_else(|| ServerError::NotFound(format!("Chain {} not found", chain_id)))
+    }
+    
+    /// Start the root signal of a chain again, with `content` as its input
+    /// if given. The new chain is linked to the one it replays.
+    pub async fn replay_chain(&self, owner: Option<&ChainOwner>, chain_id: &str, content: Option<String>) -> ServerResult<String> {
+        let record = self.chain_tracker.get(chain_id)
+            .ok_or_else(|| ServerError::NotFound(format!("Chain {} not found", chain_id)))?;
+        
+        let mut signal = NeuronSignal::forward(
+            "api-client",
+            &record.neuron_id,
+            "API",
+            &record.layer,
+            content.unwrap_or(record.input),
+        );
+        // Keep what the chain was asked for, such as its output format, but
+        // not who it was charged to or where it sat in its trace
+        let dropped = [
+            metadata_schema::keys::trace::ID, CHAIN_ID_KEY, PARENT_ID_KEY, REPLAY_OF_KEY,
+            USER_ID_KEY, ORG_ID_KEY, API_KEY_ID_KEY,
+        ];
+        signal.metadata = record.root_metadata.into_iter()
+            .filter(|(key, _)| !dropped.contains(&key.as_str()))
+            .collect();
+        signal.metadata.insert(REPLAY_OF_KEY.to_string(), chain_id.to_string());
+        
+        self.submit_signal_as(owner, signal).await
+    }
+    
+    /// Chains replayed from `chain_id`, oldest first
+    pub fn chain_replays(&self, chain_id: &str) -> Vec<String> {
+        self.chain_tracker.replays_of(chain_id)
+    }
+    
+    /// Align the signal trees of two chains and diff them node by node
+    pub async fn compare_chains(&self, a: &str, b: &str) -> ServerResult<ChainComparison> {
+        let (a, b) = (self.chain_record(a).await?, self.chain_record(b).await?);
+        Ok(ChainComparison::from_records(&a, &b, &self.config.claude.model))
+    }
+    
+    /// Get the signal tree of a chain, truncated to [`MAX_NODES`] signals
+    pub async fn chain_graph(&self, root_id: &str) -> ServerResult<ChainGraph> {
+        let record = self.chain_record(root_id).await?;
+        Ok(ChainGraph::from_record(&record, MAX_NODES))
+    }
+    
+    /// Signed receipt of a finished chain, with the inclusion proof of
+    /// `signal_id` if given
+    pub async fn chain_receipt(&self, root_id: &str, signal_id: Option<&str>) -> ServerResult<ReceiptResponse> {
+        let record = self.chain_record(root_id).await?;
+        let receipts = self.receipts.read().await.clone()
+            .ok_or_else(|| ServerError::Internal("Server has not started".to_string()))?;
+        receipts.receipt(&record, signal_id).await
+    }
+    
+    /// Data keys of every organization encrypted at rest
+    pub async fn encryption_status(&self) -> ServerResult<Vec<OrgKeyStatus>> {
+        Ok(self.tenant_keyring().await?.status())
+    }
+    
+    /// Give an organization a new data key; values under its previous key
+    /// are resealed as they are read and by the sweep
+    pub async fn rotate_org_key(&self, org_id: &str) -> ServerResult<OrgKeyStatus> {
+        let keyring = self.tenant_keyring().await?;
+        keyring.rotate(org_id).await.map_err(|e| ServerError::Internal(e.to_string()))?;
+        info!("Rotated data key of organization {}", org_id);
+        Ok(keyring.org_status(org_id))
+    }
+    
+    /// Turn encryption of an organization's new writes on or off until the
+    /// server restarts; what was sealed before stays readable
+    pub async fn set_org_encryption(&self, org_id: &str, enabled: bool) -> ServerResult<OrgKeyStatus> {
+        let keyring = self.tenant_keyring().await?;
+        keyring.set_encrypted(org_id, enabled);
+        info!("Encryption at rest for organization {} {}", org_id, if enabled { "enabled" } else { "disabled" });
+        Ok(keyring.org_status(org_id))
+    }
+    
+    /// Throughput, lag and state of every ingestion source
+    pub async fn ingestion_status(&self) -> Vec<SourceStatus> {
+        match self.ingestion.read().await.as_ref() {
+            Some(ingestion) => ingestion.status(),
+            None => Vec::new(),
+        }
+    }
+    
+    /// Stop taking messages in from an ingestion source until resumed
+    pub async fn pause_ingestion(&self, source: &str, actor: Option<&str>) -> ServerResult<SourceStatus> {
+        self.ingestion().await?.pause(source, actor)
+    }
+    
+    /// Take messages in from a paused ingestion source again
+    pub async fn resume_ingestion(&self, source: &str, actor: Option<&str>) -> ServerResult<SourceStatus> {
+        self.ingestion().await?.resume(source, actor)
+    }
+    
+    /// Newest quarantined inbound messages, of one source if given
+    pub async fn quarantined_messages(&self, source: Option<&str>) -> ServerResult<Vec<QuarantinedMessage>> {
+        self.ingestion().await?.quarantined(source).await
+    }
+    
+    /// Kafka and webhook sources; not found when none are configured
+    pub async fn ingestion(&self) -> ServerResult<Arc<Ingestion>> {
+        self.ingestion.read().await.clone()
+            .ok_or_else(|| ServerError::NotFound("No ingestion sources are configured".to_string()))
+    }
+    
+    /// Add a quality benchmark for a running neuron
+    pub async fn add_quality_benchmark(&self, benchmark: NewBenchmark) -> ServerResult<Benchmark> {
+        self.quality().await?.add_benchmark(benchmark, &self.topology.neurons()).await
+    }
+    
+    pub async fn quality_benchmarks(&self) -> ServerResult<Vec<Benchmark>> {
+        self.quality().await?.benchmarks().await
+    }
+    
+    pub async fn delete_quality_benchmark(&self, id: &str) -> ServerResult<()> {
+        self.quality().await?.delete_benchmark(id).await
+    }
+    
+    /// Score every benchmark against the running neurons now
+    pub async fn run_quality_benchmarks(&self) -> ServerResult<QualityRun> {
+        let harness = self.quality().await?;
+        harness
+            .run(RunTrigger::Manual, false, &self.topology.neurons(), &self.neuron_factory(None), None)
+            .await
+            .map_err(|e| ServerError::Internal(e.to_string()))
+    }
+    
+    /// Newest quality runs first, without their scores
+    pub async fn quality_runs(&self) -> ServerResult<Vec<QualityRun>> {
+        self.quality().await?.runs().await
+    }
+    
+    /// A quality run with its scores
+    pub async fn quality_run(&self, id: &str) -> ServerResult<QualityRun> {
+        self.quality().await?.run_details(id).await
+    }
+    
+    /// The latest quality run against the one before it, per layer
+    pub async fn quality_trends(&self) -> ServerResult<QualityTrends> {
+        self.quality().await?.trends().await
+    }
+    
+    /// Quality benchmarks; not found unless enabled
+    pub async fn quality(&self) -> ServerResult<Arc<QualityHarness>> {
+        self.quality.read().await.clone()
+            .ok_or_else(|| ServerError::NotFound("Quality benchmarks are not enabled".to_string()))
+    }
+    
+    /// Add a chain template, or a version of one, for a running neuron.
+    /// With authentication on, only system admins may.
+    pub async fn add_template(&self, user: Option<&AuthUser>, template: NewTemplate) -> ServerResult<ChainTemplate> {
+        let library = self.templates().await?;
+        if self.config.auth.enabled && !user.is_some_and(|user| user.permissions.has(&Permission::SystemAdmin)) {
+            return Err(ServerError::Forbidden("Only system admins add chain templates".to_string()));
+        }
+        library.add(template, &self.topology.neurons(), user.map(|user| user.username.as_str())).await
+    }
+    
+    /// The latest version of every chain template
+    pub async fn chain_templates(&self) -> ServerResult<Vec<ChainTemplate>> {
+        self.templates().await?.templates().await
+    }
+    
+    pub async fn chain_template(&self, name: &str, version: Option<u32>) -> ServerResult<ChainTemplate> {
+        self.templates().await?.template(name, version).await
+    }
+    
+    pub async fn chain_template_versions(&self, name: &str) -> ServerResult<Vec<ChainTemplate>> {
+        self.templates().await?.versions(name).await
+    }
+    
+    /// Render a chain template for `user` to submit: the version the
+    /// request pins, else the latest. With authentication on, the template
+    /// must allow the user's role.
+    pub async fn render_template(
+        &self,
+        user: Option<&AuthUser>,
+        name: &str,
+        request: SubmitTemplateRequest,
+    ) -> ServerResult<(ChainTemplate, RenderedTemplate)> {
+        let library = self.templates().await?;
+        let template = library.template(name, request.version).await?;
+        if self.config.auth.enabled && !template.permits(user.map(|user| user.role.as_str())) {
+            return Err(ServerError::Forbidden(format!(
+                "Template {} is only submitted by roles {}",
+                name,
+                template.spec.allowed_roles.join(", ")
+            )));
+        }
+        let org_id = user.and_then(|user| user.org_id.as_deref());
+        let rendered = library.render(&template, request.variables, &self.topology.neurons(), org_id).await?;
+        Ok((template, rendered))
+    }
+    
+    /// An artifact of a template submission. With auth enabled, one stored
+    /// for another organization than `user`'s is not found.
+    pub async fn template_artifact(&self, user: Option<&AuthUser>, id: &str) -> ServerResult<Artifact> {
+        let artifact = self.templates().await?.artifact(id).await?;
+        if self.config.auth.enabled && user.and_then(|user| user.org_id.as_deref()) != artifact.org_id.as_deref() {
+            return Err(ServerError::NotFound(format!("No artifact {}", id)));
+        }
+        Ok(artifact)
+    }
+    
+    /// Chain templates; not found unless enabled
+    pub async fn templates(&self) -> ServerResult<Arc<TemplateLibrary>> {
+        self.templates.read().await.clone()
+            .ok_or_else(|| ServerError::NotFound("Chain templates are not enabled".to_string()))
+    }
+    
+    /// Start a session for `owner` to submit chains in
+    pub async fn create_session(&self, owner: Option<&ChainOwner>) -> ServerResult<Session> {
+        self.sessions().await?.create(owner).await
+    }
+    
+    /// Live sessions of `owner`
+    pub async fn list_sessions(&self, owner: Option<&ChainOwner>) -> ServerResult<Vec<Session>> {
+        self.sessions().await?.sessions(owner).await
+    }
+    
+    /// A session of `owner` with its turns and accumulated context
+    pub async fn session_view(&self, owner: Option<&ChainOwner>, id: &str) -> ServerResult<SessionView> {
+        self.sessions().await?.view(id, owner).await
+    }
+    
+    pub async fn reset_session(&self, owner: Option<&ChainOwner>, id: &str) -> ServerResult<Session> {
+        self.sessions().await?.reset(id, owner).await
+    }
+    
+    pub async fn delete_session(&self, owner: Option<&ChainOwner>, id: &str) -> ServerResult<()> {
+        self.sessions().await?.delete(id, owner).await
+    }
+    
+    /// Sessions; not found unless enabled
+    pub async fn sessions(&self) -> ServerResult<Arc<SessionManager>> {
+        self.sessions.read().await.clone()
+            .ok_or_else(|| ServerError::NotFound("Sessions are not enabled".to_string()))
+    }
+    
+    async fn tenant_keyring(&self) -> ServerResult<Arc<TenantKeyring>> {
+        self.keyring.read().await.clone()
+            .ok_or_else(|| ServerError::InvalidInput("Encryption at rest is not configured".to_string()))
+    }
+    
+    /// Pause a layer, holding signals addressed to it until resumed
+    pub fn pause_layer(
+        &self,
+        layer: &str,
+        reason: Option<String>,
+        duration: Option<Duration>,
+        actor: Option<&str>,
+    ) -> ServerResult<LayerPause> {
+        self.layer_gate.pause(layer, reason, duration, actor)
+    }
+    
+    /// Resume a paused layer, returning how many held signals were released
+    pub async fn resume_layer(&self, layer: &str, actor: Option<&str>) -> ServerResult<usize> {
+        self.layer_gate.resume(layer, actor).await
+    }
+    
+    /// Currently paused layers
+    pub fn paused_layers(&self) -> Vec<PausedLayerStatus> {
+        self.layer_gate.paused()
+    }
+    
+    /// Whether the server is degraded and how many signals are parked
+    pub fn degraded_status(&self) -> DegradedStatus {
+        self.degraded.status()
+    }
+    
+    /// Force degraded mode on, e.g. ahead of a known provider outage
+    pub fn enter_degraded(&self, actor: Option<&str>) -> DegradedStatus {
+        self.degraded.force_enter(actor)
+    }
+    
+    /// Leave degraded mode, returning how many parked signals were released
+    pub async fn exit_degraded(&self, actor: Option<&str>) -> ServerResult<usize> {
+        self.degraded.force_exit(actor).await
+    }
+    
+    /// Outputs waiting for someone to approve or reject them
+    pub fn pending_approvals(&self) -> Vec<PendingApproval> {
+        self.approvals.pending()
+    }
+    
+    /// An output waiting for a decision
+    pub fn pending_approval(&self, id: &str) -> Option<PendingApproval> {
+        self.approvals.get(id)
+    }
+    
+    /// Gates holding outputs until someone decides on them
+    pub fn approvals(&self) -> Arc<ApprovalGates> {
+        self.approvals.clone()
+    }
+    
+    /// Approve a held output, optionally with edited content, and send it on
+    pub async fn approve_output(
+        &self,
+        id: &str,
+        content: Option<String>,
+        actor: Option<&str>,
+    ) -> ServerResult<ApprovalDecision> {
+        self.approvals.approve(id, content, actor).await
+    }
+    
+    /// Reject a held output, failing its branch
+    pub fn reject_output(&self, id: &str, reason: Option<&str>, actor: Option<&str>) -> ServerResult<ApprovalDecision> {
+        self.approvals.reject(id, reason, actor)
+    }
+    
+    /// Whether the API refuses writes for maintenance
+    pub fn read_only(&self) -> Arc<ReadOnlyGate> {
+        self.read_only.clone()
+    }
+    
+    /// Refuse writes until [`disable_read_only`](Self::disable_read_only)
+    pub fn enable_read_only(&self, reason: Option<String>, retry_after_secs: Option<u64>, actor: Option<&str>) -> ReadOnlyState {
+        self.read_only.enable(reason, retry_after_secs, actor)
+    }
+    
+    /// Accept writes again, returning how many held memory writes were written
+    pub async fn disable_read_only(&self, actor: Option<&str>) -> usize {
+        self.read_only.disable(actor).await
+    }
+    
+    /// Retention settings and the janitor's last run
+    pub fn retention_status(&self) -> RetentionStatus {
+        self.retention.status()
+    }
+    
+    /// Archive and delete whatever is past its retention now, without
+    /// waiting for the janitor
+    pub async fn run_retention(&self) -> ServerResult<RetentionReport> {
+        self.retention.run(chrono::Utc::now()).await
+    }
+    
+    /// Record the neurons and budgets as they stand now, as the snapshot
+    /// task does every `time_travel.snapshot_interval_secs`
+    pub async fn record_state_snapshot(&self) -> StateSnapshot {
+        let snapshot = StateSnapshot::take(&self.registry, &self.cost_tracker).await;
+        self.time_travel.record(snapshot.clone());
+        snapshot
+    }
+    
+    /// In-flight chains, queue depths, circuit breakers and budgets as they
+    /// stood at `at`, rebuilt from the snapshot before it
+    pub async fn state_at(&self, at: chrono::DateTime<chrono::Utc>) -> ServerResult<PointInTime> {
+        if !self.config.time_travel.enabled {
+            return Err(ServerError::NotFound("Time travel is not enabled".to_string()));
+        }
+        self.time_travel.state_at(at).await
+    }
+    
+    /// Size of the hot and cold memory tiers and the tiering job's last run
+    pub async fn tiering_status(&self) -> ServerResult<TieringStatus> {
+        let tiering = self.memory_tiering.read().await.clone()
+            .ok_or_else(|| ServerError::ConfigError("Memory system is disabled".to_string()))?;
+        tiering.status().await
+    }
+    
+    /// Move idle memory to the cold tier now, without waiting for the
+    /// tiering job
+    pub async fn run_tiering(&self) -> ServerResult<TieringReport> {
+        let tiering = self.memory_tiering.read().await.clone()
+            .ok_or_else(|| ServerError::ConfigError("Memory system is disabled".to_string()))?;
+        tiering.run(chrono::Utc::now()).await
+    }
+    
+    /// Today's spending on memory summaries and the summarization job's
+    /// last run
+    pub async fn summarization_status(&self) -> ServerResult<SummarizationStatus> {
+        let summarization = self.memory_summarization.read().await.clone()
+            .ok_or_else(|| ServerError::ConfigError("Memory system is disabled".to_string()))?;
+        Ok(summarization.status())
+    }
+    
+    /// Summarize memory namespaces near their quota now, without waiting
+    /// for the summarization job
+    pub async fn run_summarization(&self) -> ServerResult<SummarizationReport> {
+        let summarization = self.memory_summarization.read().await.clone()
+            .ok_or_else(|| ServerError::ConfigError("Memory system is disabled".to_string()))?;
+        summarization.run(chrono::Utc::now()).await
+    }
+    
+    /// VACUUM and ANALYZE the server's SQLite databases
+    pub async fn compact_databases(&self) -> ServerResult<Vec<CompactReport>> {
+        self.retention.compact().await
+    }
+    
+    /// Timeout settings and what each neuron's next provider call gets
+    pub fn timeout_status(&self) -> TimeoutStatus {
+        self.timeouts.status()
+    }
+    
+    /// Turn adaptive timeouts on or off; off, every call gets its layer's
+    /// static timeout again
+    pub fn set_adaptive_timeouts(&self, enabled: bool) -> TimeoutStatus {
+        self.timeouts.set_adaptive(enabled);
+        self.timeouts.status()
+    }
+    
+    /// Compliance and error budget of each configured SLO
+    pub fn slo_status(&self) -> Vec<SloStatus> {
+        self.slo.status(chrono::Utc::now())
+    }
+    
+    /// Scaling actions taken by the self-organizer, newest first
+    pub async fn scaling_events(&self) -> Vec<ScalingEvent> {
+        self.self_organizer.read().await.as_ref()
+            .map(|organizer| organizer.events())
+            .unwrap_or_default()
+    }
+    
+    /// Signals rejected by full neuron queues, oldest first
+    pub fn dead_letters(&self) -> Vec<DeadLetter> {
+        self.registry.dead_letters().list()
+    }
+    
+    /// Send the dead-lettered signal `signal_id` to its neuron again, as the
+    /// root of a new chain. The letter leaves the queue unless the signal
+    /// cannot be submitted.
+    pub async fn retry_dead_letter(&self, owner: Option<&ChainOwner>, signal_id: &str) -> ServerResult<String> {
+        let dead_letters = self.registry.dead_letters();
+        let letter = dead_letters.take(signal_id)
+            .ok_or_else(|| ServerError::NotFound(format!("Dead letter {} not found", signal_id)))?;
+        if letter.signal.propagation_type != PropagationType::Forward {
+            dead_letters.restore(letter);
+            return Err(ServerError::InvalidInput("Only forward signals can be retried".to_string()));
+        }
+        
+        let original = &letter.signal;
+        let mut signal = NeuronSignal::forward(
+            "api-client",
+            &original.to_neuron,
+            "API",
+            &original.layer_to,
+            original.payload.activation.content.clone(),
+        );
+        // Keep what the chain was asked for, not where the signal sat in it
+        let kept = [
+            metadata_schema::keys::output::FORMAT, metadata_schema::keys::output::SCHEMA, TAGS_KEY,
+        ];
+        signal.metadata = original.metadata.iter()
+            .filter(|(key, _)| kept.contains(&key.as_str()))
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect();
+        
+        match self.submit_signal_as(owner, signal).await {
+            Ok(chain_id) => Ok(chain_id),
+            Err(e) => {
+                dead_letters.restore(letter);
+                Err(e)
+            }
+        }
+    }
+    
+    /// Registered signal metadata namespaces and keys
+    pub fn metadata_schema(&self) -> SchemaDescription {
+        metadata_schema::global().read().describe()
+    }
+    
+    /// Emergent patterns, phase transitions and complexity of the signal flow
+    pub async fn emergence_report(&self) -> ServerResult<EmergenceReport> {
+        self.intelligence.observe_emergence().await
+            .map_err(|e| ServerError::Internal(e.to_string()))
+    }
+    
+    /// Solutions for a creative challenge, with the candidates rejected by
+    /// its constraints
+    pub async fn create_solutions(&self, challenge: Challenge) -> ServerResult<CreationReport> {
+        self.intelligence.create_report(challenge).await
+            .map_err(|e| ServerError::Internal(e.to_string()))
+    }
+    
+    /// Signal flow history; consciousness measurements recorded here feed
+    /// phase transition detection
+    pub fn signal_flow(&self) -> &Arc<SignalFlowHistory> {
+        &self.signal_flow
+    }
+    
+    /// Get chain tracker
+    pub fn chain_tracker(&self) -> Arc<ChainTracker> {
+        self.chain_tracker.clone()
+    }
+    
+    /// Get webhook manager
+    pub fn webhooks(&self) -> Arc<WebhookManager> {
+        self.webhooks.clone()
+    }
+    
+    /// Usage of an organization over the current day, week or month
+    pub async fn org_usage(&self, org_id: &str, period: BudgetPeriod, compare: bool) -> ServerResult<OrgUsage> {
+        let store = self.org_usage.as_ref()
+            .ok_or_else(|| ServerError::InvalidInput("Organization usage needs authentication enabled".to_string()))?;
+        store.report(org_id, period, chrono::Utc::now(), compare).await
+            .map_err(|e| ServerError::Internal(e.to_string()))
+    }
+    
+    /// Delivered billing files, of one organization if given, dated within
+    /// `[from, to]`
+    pub async fn billing_manifest(
+        &self,
+        org_id: Option<&str>,
+        from: Option<chrono::NaiveDate>,
+        to: Option<chrono::NaiveDate>,
+    ) -> ServerResult<Vec<ExportedFile>> {
+        self.billing_export().await?.manifest(org_id, from, to).await
+            .map_err(|e| ServerError::Internal(e.to_string()))
+    }
+    
+    /// Export one finished day, or the lookback window without one, now
+    pub async fn run_billing_export(&self, date: Option<chrono::NaiveDate>) -> ServerResult<ExportReport> {
+        let exporter = self.billing_export().await?;
+        match date {
+            Some(date) => exporter.export(date, chrono::Utc::now()).await,
+            None => exporter.run(chrono::Utc::now()).await,
+        }
+    }
+    
+    /// Billing export; not found unless enabled
+    pub async fn billing_export(&self) -> ServerResult<Arc<BillingExporter>> {
+        self.billing_export.read().await.clone()
+            .ok_or_else(|| ServerError::NotFound("Billing export is not enabled".to_string()))
+    }
+    
+    /// Differential privacy of exported usage reports
+    pub fn report_privacy(&self) -> Arc<ReportPrivacy> {
+        self.report_privacy.clone()
+    }
+    
+    /// Shadowing of submissions to the staging server
+    pub fn shadow(&self) -> Arc<ShadowMirror> {
+        self.shadow.clone()
+    }
+    
+    /// CPU profiles and the poll monitor of signal tasks
+    pub fn profiler(&self) -> Arc<Profiler> {
+        self.profiler.clone()
+    }
+    
+    /// Code generation jobs and their progress
+    pub fn codegen_jobs(&self) -> Arc<CodegenJobs> {
+        self.codegen_jobs.clone()
+    }
+    
+    /// Get chain limiter
+    pub fn chain_limiter(&self) -> Arc<ChainLimiter> {
+        self.chain_limiter.clone()
+    }
+    
+    /// Tool call ledgers of running chains
+    pub fn tool_sandbox(&self) -> Arc<ToolSandbox> {
+        self.tool_sandbox.clone()
+    }
+    
+    /// Every config value in effect and the layer it came from, secrets
+    /// redacted
+    pub fn effective_config(&self) -> Vec<EffectiveValue> {
+        self.config_layers.read().effective()
+    }
+    
+    /// Override a config value at runtime and apply it at once. Values that
+    /// need a restart are rejected.
+    pub fn set_config_override(&self, path: &str, value: serde_json::Value) -> ServerResult<()> {
+        let mut layers = self.config_layers.write();
+        let mut next = layers.clone();
+        next.set_override(path, value)
+            .map_err(|e| ServerError::InvalidInput(e.to_string()))?;
+        self.apply_config(&layers, &next)?;
+        info!("Config override set: {}", path);
+        *layers = next;
+        Ok(())
+    }
+    
+    /// Drop a runtime config override, going back to the file or
+    /// environment value
+    pub fn clear_config_override(&self, path: &str) -> ServerResult<()> {
+        let mut layers = self.config_layers.write();
+        let mut next = layers.clone();
+        if !next.clear_override(path).map_err(|e| ServerError::InvalidInput(e.to_string()))? {
+            return Err(ServerError::NotFound(format!("No config override for {}", path)));
+        }
+        self.apply_config(&layers, &next)?;
+        info!("Config override cleared: {}", path);
+        *layers = next;
+        Ok(())
+    }
+    
+    /// Push changed runtime-overridable values to the components using them
+    fn apply_config(&self, previous: &LayeredConfig, next: &LayeredConfig) -> ServerResult<()> {
+        let (before, after) = (previous.config(), next.config());
+        
+        if before.monitoring.log_level != after.monitoring.log_level {
+            let level = &after.monitoring.log_level;
+            level.parse::<tracing::level_filters::LevelFilter>()
+                .map_err(|_| ServerError::InvalidInput(format!("Invalid log level '{}'", level)))?;
+            // Without an override, the directives from startup apply again
+            let directives = next.overrides().contains_key("monitoring.log_level").then_some(level.as_str());
+            if let Some(levels) = crate::logging::log_levels() {
+                levels.set_base(directives).map_err(ServerError::InvalidInput)?;
+            }
+        }
+        self.chain_limiter.set_config(after.limits.clone());
+        self.tool_sandbox.set_config(after.tool_sandbox.clone());
+        self.cost_tracker.set_limits(&after.claude.cost_controls);
+        self.registry.warm_pool().set_config(&after.warm_pool);
+        Ok(())
+    }
+    
+    /// Size and occupancy of every warm pool
+    pub fn warm_pools(&self) -> Vec<WarmPoolStatus> {
+        self.registry.warm_pool().status()
+    }
+    
+    /// Resize the warm pool of a neuron type, as a runtime override of
+    /// `warm_pool.sizes.<neuron_type>`
+    pub fn set_warm_pool_size(&self, neuron_type: &str, size: usize) -> ServerResult<()> {
+        if neuron_type.is_empty() || neuron_type.contains('.') {
+            return Err(ServerError::InvalidInput(format!("Invalid neuron type '{}'", neuron_type)));
+        }
+        self.set_config_override(&format!("warm_pool.sizes.{}", neuron_type), serde_json::json!(size))
+    }
+
+    /// The neuron topology, with applied patches
+    pub fn topology(&self) -> Vec<NeuronConfig> {
+        self.topology.neurons()
+    }
+
+    /// Validate a topology patch and return what it would change, with the
+    /// token that applies it
+    pub fn propose_topology_patch(&self, ops: Vec<PatchOp>) -> ServerResult<PatchProposal> {
+        self.topology.propose(ops)
+    }
+
+    /// Apply a proposed topology patch to the running neurons
+    pub async fn apply_topology_patch(&self, token: &str, actor: Option<&str>) -> ServerResult<AppliedPatch> {
+        let _applying = self.topology_apply.lock().await;
+        let patch = self.topology.take(token)?;
+        self.check_quality_gate(&patch).await?;
+        self.swap_neurons(&patch).await?;
+        let changed = changed_ids(&patch);
+        let applied = self.topology.commit(patch, actor)?;
+        self.rescore_quality(&changed).await;
+        Ok(applied)
+    }
+
+    /// Undo the latest applied topology patch
+    pub async fn revert_topology_patch(&self, actor: Option<&str>) -> ServerResult<AppliedPatch> {
+        let _applying = self.topology_apply.lock().await;
+        let patch = self.topology.take_revert()?;
+        self.swap_neurons(&patch).await?;
+        let changed = changed_ids(&patch);
+        let reverted = self.topology.commit(patch, actor)?;
+        self.rescore_quality(&changed).await;
+        Ok(reverted)
+    }
+
+    /// With the quality gate on, refuse a patch whose changed neurons
+    /// score significantly worse on their benchmarks than the running ones
+    async fn check_quality_gate(&self, patch: &PreparedPatch) -> ServerResult<()> {
+        let Some(harness) = self.quality.read().await.clone().filter(|harness| harness.config().gate) else {
+            return Ok(());
+        };
+        let report = harness
+            .gate(&patch.before, &patch.after, &changed_ids(patch), &self.neuron_factory(None))
+            .await
+            .map_err(|e| ServerError::Forbidden(e.to_string()))?;
+        match report.refusal() {
+            Some(refusal) => {
+                warn!("{}", refusal);
+                Err(ServerError::Forbidden(refusal))
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Score the benchmarks again after neurons they send to changed
+    async fn rescore_quality(&self, changed: &HashSet<String>) {
+        let Some(harness) = self.quality.read().await.clone().filter(|harness| harness.config().run_on_change) else {
+            return;
+        };
+        match harness.benchmarks().await {
+            Ok(benchmarks) if benchmarks.iter().any(|benchmark| changed.contains(&benchmark.neuron)) => {
+                self.spawn_quality_run(harness, RunTrigger::TopologyChange);
+            }
+            Ok(_) => {}
+            Err(e) => warn!("Failed to read quality benchmarks: {}", e),
+        }
+    }
+
+    /// Score the running neurons in the background
+    fn spawn_quality_run(&self, harness: Arc<QualityHarness>, trigger: RunTrigger) {
+        let neurons = self.topology.neurons();
+        let factory = self.neuron_factory(None);
+        tokio::spawn(async move {
+            if let Err(e) = harness.run(trigger, false, &neurons, &factory, None).await {
+                error!("Quality run failed: {}", e);
+            }
+        });
+    }
+
+    /// Replace the neurons a patch changes and drop the ones it removes.
+    /// Replacements are built first, so a neuron failing to build leaves
+    /// the running topology as it was.
+    async fn swap_neurons(&self, patch: &PreparedPatch) -> ServerResult<()> {
+        let factory = self.neuron_factory.read().clone()
+            .ok_or_else(|| ServerError::Internal("Neurons are not running yet".to_string()))?;
+        let rebuilt = patch.changed().into_iter()
+            .map(|config| factory(config.clone()))
+            .collect::<Result<Vec<_>>>()
+            .map_err(|e| ServerError::InvalidInput(format!("Cannot build neuron: {}", e)))?;
+
+        let neuron_error = |e: Error| ServerError::NeuronError(e.to_string());
+        for id in patch.removed() {
+            self.routing_table.remove_routes(id);
+            self.registry.remove(id).await.map_err(neuron_error)?;
+        }
+        for neuron in rebuilt {
+            let id = neuron.id.clone();
+            self.registry.remove(&id).await.map_err(neuron_error)?;
+            self.routing_table.set_routes(&id, neuron.config.forward_connections.clone());
+            self.registry.register(neuron).await.map_err(neuron_error)?;
+        }
+        self.metrics.set_active_neurons(patch.after.len() as u64);
+        Ok(())
+    }
+
+    /// Current usage against the limits of a user
+    pub fn limit_usage(&self, owner: &ChainOwner) -> LimitUsage {
+        self.chain_limiter.usage(owner, self.scheduler.active_for(&owner.user_id))
+    }
+    
+    /// Search neuron memory
+    pub async fn search_memory(&self, search: MemorySearch) -> ServerResult<Vec<MemoryEntry>> {
+        let memory = self.memory.read().await.clone()
+            .ok_or_else(|| ServerError::ConfigError("Memory system is disabled".to_string()))?;
+        memory.store().search(search).await
+            .map_err(|e| ServerError::Internal(e.to_string()))
+    }
+    
+    /// Search `namespace` as `principal` on `layer`, under the namespace
+    /// access policy
+    pub async fn read_memory(
+        &self,
+        principal: &str,
+        layer: &str,
+        namespace: &MemoryNamespace,
+        search: MemorySearch,
+    ) -> ServerResult<Vec<MemoryEntry>> {
+        let memory = self.memory.read().await.clone()
+            .ok_or_else(|| ServerError::ConfigError("Memory system is disabled".to_string()))?;
+        memory.read(principal, layer, namespace, search).await
+            .map_err(|e| match e {
+                Error::PermissionDenied(reason) => ServerError::Forbidden(reason),
+                e => ServerError::Internal(e.to_string()),
+            })
+    }
+    
+    /// API spend so far
+    pub async fn cost_stats(&self) -> CostStats {
+        self.cost_tracker.get_stats().await
+    }
+    
+    /// Tagged spend of the current `period`, grouped by the value of `key`
+    pub fn cost_by_tag(&self, key: &str, period: BudgetPeriod) -> ServerResult<TagSpendReport> {
+        self.cost_tracker.spend_by_tag(key, period)
+            .map_err(|e| ServerError::InvalidInput(e.to_string()))
+    }
+    
+    /// Prompt tokens by layer and section over the current `period`
+    pub fn prompt_composition_report(&self, period: BudgetPeriod) -> PromptCompositionReport {
+        self.prompt_accounting.report(period)
+    }
+    
+    /// Per-namespace memory usage against limits
+    pub async fn memory_stats(&self) -> ServerResult<Vec<NamespaceStats>> {
+        let memory = self.memory.read().await.clone()
+            .ok_or_else(|| ServerError::ConfigError("Memory system is disabled".to_string()))?;
+        memory.stats().await
+            .map_err(|e| ServerError::Internal(e.to_string()))
+    }
+    
+    /// Writes to moderated namespaces awaiting review, newest first
+    pub async fn pending_memory(&self) -> ServerResult<Vec<MemoryEntry>> {
+        let memory = self.memory.read().await.clone()
+            .ok_or_else(|| ServerError::ConfigError("Memory system is disabled".to_string()))?;
+        memory.pending(None, MAX_PENDING_LISTED).await
+            .map_err(|e| ServerError::Internal(e.to_string()))
+    }
+    
+    /// Approve a pending memory write, making it visible to prompts
+    pub async fn approve_memory(&self, id: Uuid) -> ServerResult<()> {
+        let memory = self.memory.read().await.clone()
+            .ok_or_else(|| ServerError::ConfigError("Memory system is disabled".to_string()))?;
+        match memory.approve(id).await {
+            Ok(true) => Ok(()),
+            Ok(false) => Err(ServerError::NotFound(format!("No pending memory write {}", id))),
+            Err(e) => Err(ServerError::Internal(e.to_string())),
+        }
+    }
+    
+    /// Reject a pending memory write, keeping it with `reason`
+    pub async fn reject_memory(&self, id: Uuid, reason: &str) -> ServerResult<()> {
+        let memory = self.memory.read().await.clone()
+            .ok_or_else(|| ServerError::ConfigError("Memory system is disabled".to_string()))?;
+        match memory.reject(id, reason).await {
+            Ok(true) => Ok(()),
+            Ok(false) => Err(ServerError::NotFound(format!("No pending memory write {}", id))),
+            Err(e) => Err(ServerError::Internal(e.to_string())),
+        }
+    }
+    
+    /// Memory dump lines of entries in `scope`, optionally of one namespace
+    /// and neuron
+    pub async fn export_memory(
+        &self,
+        namespace: Option<&str>,
+        neuron_id: Option<&str>,
+        scope: &DumpScope,
+    ) -> ServerResult<Vec<String>> {
+        let memory = self.memory.read().await.clone()
+            .ok_or_else(|| ServerError::ConfigError("Memory system is disabled".to_string()))?;
+        let embeddings = EmbeddingGenerator::new(self.config.memory.embedding_dimension);
+        memory_manager::export_dump(memory.store().as_ref(), namespace, neuron_id, scope, &embeddings).await
+            .map_err(|e| ServerError::Internal(e.to_string()))
+    }
+    
+    /// Import a memory dump of entries in `scope`, bypassing namespace
+    /// policies and quotas
+    pub async fn import_memory(&self, dump: &str, options: &ImportOptions, scope: &DumpScope) -> ServerResult<ImportReport> {
+        let memory = self.memory.read().await.clone()
+            .ok_or_else(|| ServerError::ConfigError("Memory system is disabled".to_string()))?;
+        let embeddings = EmbeddingGenerator::new(self.config.memory.embedding_dimension);
+        memory_manager::import_dump(memory.store().as_ref(), dump, options, scope, &embeddings).await
+            .map_err(|e| match e {
+                Error::InvalidInput(reason) => ServerError::InvalidInput(reason),
+                Error::PermissionDenied(reason) => ServerError::Forbidden(reason),
+                e => ServerError::Internal(e.to_string()),
+            })
+    }
+    
+    /// List all neurons
+    pub async fn list_neurons(&self) -> ServerResult<Vec<NeuronInfo>> {
+        Ok(self.registry.list_all().await)
+    }
+    
+    /// Neurons that changed after version `since` of the change feed, or
+    /// every neuron without one
+    pub async fn neuron_changes(&self, since: Option<u64>) -> NeuronChanges {
+        self.registry.changes_since(since).await
+    }
+    
+    /// Get specific neuron info
+    pub async fn get_neuron_info(&self, neuron_id: &str) -> ServerResult<NeuronInfo> {
+        self.registry.get_info(neuron_id).await
+            .ok_or_else(|| ServerError::NotFound(format!("Neuron {} not found", neuron_id)))
+    }
+    
+    /// Get neuron health
     pub async fn get_neuron_health(&self, neuron_id: &str) -> ServerResult<NeuronHealth> {
         let health_map = self.registry.health_check().await;
         health_map.get(neuron_id)
@@ -490,12 +2325,22 @@ impl HAL9Server {
     
     /// Subscribe to server events
     pub async fn subscribe_to_events(&self) -> broadcast::Receiver<WsMessage> {
-        self.event_tx.subscribe()
+        self.events.subscribe()
+    }
+    
+    /// Numbered server events, kept for resuming SSE clients
+    pub fn event_log(&self) -> Arc<EventLog> {
+        self.events.clone()
+    }
+    
+    /// Subscribe to crashes and restarts of worker processes
+    pub fn subscribe_worker_events(&self) -> broadcast::Receiver<WorkerEvent> {
+        self.worker_events.subscribe()
     }
     
     /// Broadcast an event
     pub fn broadcast_event(&self, event: WsMessage) {
-        let _ = self.event_tx.send(event);
+        self.events.send(event);
     }
     
     
@@ -540,6 +2385,86 @@ impl HAL9Server {
         }
     }
     
+    /// Forward finished chains to their owner's webhooks and charge their
+    /// tokens to the API key they were started with
+    fn start_chain_notifier(&self) {
+        let mut finished = self.chain_tracker.subscribe();
+        let chain_tracker = self.chain_tracker.clone();
+        let webhooks = self.webhooks.clone();
+        let api_key_manager = self.api_key_manager.clone();
+        
+        tokio::spawn(async move {
+            loop {
+                match finished.recv().await {
+                    Ok(result) => {
+                        let record = chain_tracker.get(&result.chain_id);
+                        if let (Some(manager), Some(key_id)) = (&api_key_manager, record.as_ref().and_then(|r| r.api_key_id.as_deref())) {
+                            let tokens = result.prompt_tokens + result.completion_tokens;
+                            if let Err(e) = manager.record_token_usage(key_id, tokens).await {
+                                error!("Failed to charge chain {} to API key {}: {}", result.chain_id, key_id, e);
+                            }
+                        }
+                        let owner = record
+                            .and_then(|record| record.org_id.or(record.user_id))
+                            .unwrap_or_else(|| "anonymous".to_string());
+                        webhooks.emit_chain(&owner, &result);
+                    }
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        error!("Chain notifier lagged, {} chain events not delivered", skipped);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        });
+    }
+    
+    /// Add finished chains submitted in a session to it as turns
+    fn start_session_recorder(&self, sessions: Arc<SessionManager>) {
+        let mut finished = self.chain_tracker.subscribe();
+        let chain_tracker = self.chain_tracker.clone();
+        
+        tokio::spawn(async move {
+            loop {
+                match finished.recv().await {
+                    Ok(result) => {
+                        let Some(session_id) = chain_tracker.get(&result.chain_id)
+                            .and_then(|record| record.root_metadata.get(SESSION_ID_KEY).cloned()) else {
+                            continue;
+                        };
+                        // A chain without output has nothing to continue from
+                        let Some(output) = &result.final_output else {
+                            debug!("Chain {} of session {} ended without output", result.chain_id, session_id);
+                            continue;
+                        };
+                        if let Err(e) = sessions.record_turn(&session_id, &result.chain_id, &result.input, output).await {
+                            error!("Failed to add chain {} to session {}: {}", result.chain_id, session_id, e);
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        error!("Session recorder lagged, {} chains not added to their sessions", skipped);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        });
+    }
+    
+    /// Record a state snapshot every `time_travel.snapshot_interval_secs`
+    fn start_snapshot_recorder(&self) {
+        let registry = self.registry.clone();
+        let cost_tracker = self.cost_tracker.clone();
+        let time_travel = self.time_travel.clone();
+        let period = Duration::from_secs(self.config.time_travel.snapshot_interval_secs);
+        
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(period);
+            loop {
+                interval.tick().await;
+                time_travel.record(StateSnapshot::take(&registry, &cost_tracker).await);
+            }
+        });
+    }
+    
     /// Start periodic metrics reporting
     async fn start_metrics_reporter(&self) {
         let metrics = self.metrics.clone();
@@ -594,6 +2519,92 @@ impl HAL9Server {
     }
 }
 
+/// IDs of the neurons a patch adds or changes
+fn changed_ids(patch: &PreparedPatch) -> HashSet<String> {
+    patch.changed().into_iter().map(|neuron| neuron.id.clone()).collect()
+}
+
+/// Create Claude instance based on configuration. Mock instances draw
+/// their randomness from `rng`.
+fn create_claude_instance(
+    config: &ClaudeConfig,
+    cost_tracker: &Arc<CostTracker>,
+    models: &Arc<ModelRegistry>,
+    layer: &str,
+    rng: SimRng,
+) -> Result<Box<dyn ClaudeInterface>> {
+    let mock = |rng: SimRng| {
+        let mut mock = MockClaude::new(layer, config);
+        mock.set_rng(rng);
+        Box::new(mock)
+    };
+    
+    match config.mode.as_str() {
+        "mock" => {
+            info!("Creating mock Claude for layer {}", layer);
+            Ok(mock(rng))
+        }
+        "api" => {
+            info!("Creating Claude API client for layer {}", layer);
+            let api_key = config.api_key.as_ref().map(|key| key.expose().to_string())
+                .or_else(|| std::env::var("ANTHROPIC_API_KEY").ok())
+                .ok_or_else(|| Error::Config("Claude API key not found".to_string()))?;
+                
+            let mut api_client = ClaudeAPIClient::new(
+                api_key,
+                config.model.clone(),
+                layer,
+                config.temperature,
+                config.max_tokens,
+            );
+            
+            // Set cost tracker
+            api_client.set_cost_tracker(cost_tracker.clone());
+            api_client.set_model_registry(models.clone());
+            
+            // If fallback is enabled, wrap in FallbackClaude
+            if config.fallback_to_mock {
+                info!("Enabling fallback to mock mode for layer {}", layer);
+                let mock_client = mock(rng);
+                Ok(Box::new(FallbackClaude::new(Box::new(api_client), mock_client)))
+            } else {
+                Ok(Box::new(api_client))
+            }
+        }
+        "hybrid" | "auto" => {
+            info!("Creating hybrid Claude for layer {} (mode: {})", layer, config.mode);
+            Ok(Box::new(HybridClaude::new(
+                layer,
+                config,
+                cost_tracker.clone(),
+                models.clone(),
+            )?))
+        }
+        mode => Err(Error::Config(format!("Unknown Claude mode: {}", mode))),
+    }
+}
+
+/// Send worker crashes and restarts to event subscribers as neuron state
+/// changes
+async fn forward_worker_events(mut events: broadcast::Receiver<WorkerEvent>, event_log: Arc<EventLog>) {
+    loop {
+        let event = match events.recv().await {
+            Ok(event) => event,
+            Err(broadcast::error::RecvError::Lagged(_)) => continue,
+            Err(broadcast::error::RecvError::Closed) => return,
+        };
+        let (old_state, new_state) = match event.kind {
+            WorkerEventKind::Crashed { .. } => (NeuronState::Running, NeuronState::Failed),
+            WorkerEventKind::Restarted { .. } => (NeuronState::Failed, NeuronState::Running),
+        };
+        event_log.send(WsMessage::NeuronStateChange {
+            neuron_id: event.neuron_id,
+            old_state: format!("{:?}", old_state),
+            new_state: format!("{:?}", new_state),
+        });
+    }
+}
+
 /// Server status information
 #[derive(Debug, serde::Serialize)]
 pub struct ServerStatus {
@@ -611,16 +2622,12 @@ pub struct ExtendedServerStatus {
     pub neurons: Vec<NeuronInfo>,
     pub metrics: crate::metrics::MetricsSnapshot,
     pub network_status: Option<NetworkStatus>,
+    pub paused_layers: Vec<PausedLayerStatus>,
+    pub degraded: DegradedStatus,
+    pub read_only: ReadOnlyStatus,
 }
 
-/// Neuron information
-#[derive(Debug, Clone, serde::Serialize)]
-pub struct NeuronInfo {
-    pub id: String,
-    pub layer: String,
-    pub state: String,
-    pub is_healthy: bool,
-}
+pub use hal9_api_types::NeuronInfo;
 
 /// MCP tool metrics
 #[derive(Debug, Clone, serde::Serialize)]
@@ -0,0 +1,578 @@
+//! Sessions: multi-turn conversations of chains
+//!
+//! A session belongs to the user, and organization, that created it. A
+//! chain submitted with a session ID is the next turn of the conversation:
+//! its root signal carries the conversation so far, a rolling summary and
+//! the latest `sessions.recent_turns` turns quoted in full, which the entry
+//! neuron's prompt shows within `sessions.max_context_tokens`. Once the
+//! chain finishes, its input and final output are added as a turn and the
+//! summary model folds them into the summary.
+//!
+//! Sessions expire `sessions.ttl_secs` after they were last used. Only the
+//! latest `sessions.max_turns` turns are kept; older ones live on in the
+//! summary, which is compacted by the summary model when it grows past
+//! `sessions.max_summary_tokens`.
+//!
+//! Sessions and their turns are kept in the `chat_sessions` and
+//! `chat_session_turns` tables of the server database, from the
+//! `008_chat_sessions` migration.
+
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::Row;
+use tokio::sync::Mutex;
+use tracing::{debug, info, warn};
+
+use hal9_core::{
+    config::{DatabaseConfig, SessionsConfig},
+    metadata_schema::keys,
+    Error, NeuronSignal, Result, ServerConfig,
+};
+
+use crate::chain_limits::ChainOwner;
+use crate::claude::ClaudeInterface;
+use crate::database::{on_pool, DatabasePool};
+use crate::database_migrations;
+use crate::error::{ServerError, ServerResult};
+use crate::prompt_composition::Tokenizer;
+
+/// Metadata key of the session a chain is a turn of
+pub const SESSION_ID_KEY: &str = keys::session::ID;
+
+/// Metadata key of the conversation a root signal's prompt shows
+pub const SESSION_CONTEXT_KEY: &str = keys::session::CONTEXT;
+
+/// Check the session settings
+pub fn validate(config: &SessionsConfig, database: &DatabaseConfig) -> Result<()> {
+    let invalid = |message: &str| Err(Error::Config(format!("Invalid sessions config: {}", message)));
+    if config.enabled && database.url.is_none() {
+        return invalid("sessions are kept in the server database; set database.url");
+    }
+    if config.ttl_secs == 0 {
+        return invalid("ttl_secs must be positive");
+    }
+    if config.max_turns == 0 {
+        return invalid("max_turns must be positive");
+    }
+    if config.recent_turns > config.max_turns {
+        return invalid("recent_turns must not exceed max_turns");
+    }
+    if config.max_summary_tokens == 0 || config.max_context_tokens == 0 {
+        return invalid("max_summary_tokens and max_context_tokens must be positive");
+    }
+    if config.max_summary_tokens > config.max_context_tokens {
+        return invalid("max_summary_tokens must not exceed max_context_tokens");
+    }
+    if config.summary_model.trim().is_empty() {
+        return invalid("summary_model must not be empty");
+    }
+    Ok(())
+}
+
+/// A conversation of chains
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Session {
+    pub id: String,
+    /// User who created the session, if authenticated
+    pub user_id: Option<String>,
+    pub org_id: Option<String>,
+    /// Rolling summary of every turn so far
+    pub summary: String,
+    /// Turns taken, including those only the summary keeps
+    pub turns: u64,
+    pub created_at: DateTime<Utc>,
+    pub last_active_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl Session {
+    /// Whether `owner` may use the session: its creator, or a member of the
+    /// organization it was created in. Sessions created without
+    /// authentication are open to anyone.
+    pub fn permits(&self, owner: Option<&ChainOwner>) -> bool {
+        match (&self.user_id, owner) {
+            (None, _) => true,
+            (Some(_), None) => false,
+            (Some(user_id), Some(owner)) => {
+                *user_id == owner.user_id || (self.org_id.is_some() && self.org_id == owner.org_id)
+            }
+        }
+    }
+}
+
+/// A finished chain of a session
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SessionTurn {
+    /// Turns of a session count up from 1
+    pub seq: u64,
+    pub chain_id: String,
+    pub input: String,
+    pub output: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A session with its kept turns and the context its next chain gets
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionView {
+    #[serde(flatten)]
+    pub session: Session,
+    /// Kept turns, oldest first
+    pub history: Vec<SessionTurn>,
+    /// Conversation the next chain's prompt shows
+    pub context: String,
+    pub context_tokens: u64,
+}
+
+/// `text` cut to at most `max_tokens` tokens
+fn truncate_tokens(tokenizer: Tokenizer, text: &str, max_tokens: usize) -> &str {
+    let mut end = text.len();
+    loop {
+        let tokens = tokenizer.count(&text[..end]) as usize;
+        if tokens <= max_tokens {
+            return &text[..end];
+        }
+        end = end * max_tokens / tokens;
+        while !text.is_char_boundary(end) {
+            end -= 1;
+        }
+    }
+}
+
+fn quote(turn: &SessionTurn) -> String {
+    format!("[Turn {}]\nUser: {}\nResult: {}\n", turn.seq, turn.input, turn.output)
+}
+
+fn internal(e: Error) -> ServerError {
+    ServerError::Internal(e.to_string())
+}
+
+/// Sessions, and the summary model keeping their summaries
+pub struct SessionManager {
+    config: SessionsConfig,
+    store: SessionStore,
+    summarizer: Box<dyn ClaudeInterface>,
+    /// Tokenizer of the neurons' model, the context is budgeted with
+    tokenizer: Tokenizer,
+    /// Turns are added one at a time, so each summary update sees the last
+    turns: Mutex<()>,
+}
+
+impl SessionManager {
+    /// Connect to the server database if sessions are enabled; the session
+    /// tables come with its migrations
+    pub async fn open(config: &ServerConfig, summarizer: Box<dyn ClaudeInterface>) -> Result<Option<Self>> {
+        if !config.sessions.enabled {
+            return Ok(None);
+        }
+        let pool = database_migrations::connect(&config.database)
+            .await?
+            .ok_or_else(|| Error::Config("Sessions need database.url".to_string()))?;
+        info!("Sessions kept in the {:?} database", pool.database_type());
+        let tokenizer = Tokenizer::for_model(&config.claude.model);
+        Ok(Some(Self::new(config.sessions.clone(), SessionStore::new(pool), summarizer, tokenizer)))
+    }
+
+    pub fn new(config: SessionsConfig, store: SessionStore, summarizer: Box<dyn ClaudeInterface>, tokenizer: Tokenizer) -> Self {
+        Self { config, store, summarizer, tokenizer, turns: Mutex::new(()) }
+    }
+
+    fn expiry(&self, from: DateTime<Utc>) -> DateTime<Utc> {
+        from + Duration::seconds(self.config.ttl_secs.min(i64::MAX as u64 / 1000) as i64)
+    }
+
+    /// Start a session for `owner`, dropping expired ones
+    pub async fn create(&self, owner: Option<&ChainOwner>) -> ServerResult<Session> {
+        let now = Utc::now();
+        self.store.delete_expired(now).await.map_err(internal)?;
+        let session = Session {
+            id: uuid::Uuid::new_v4().to_string(),
+            user_id: owner.map(|owner| owner.user_id.clone()),
+            org_id: owner.and_then(|owner| owner.org_id.clone()),
+            summary: String::new(),
+            turns: 0,
+            created_at: now,
+            last_active_at: now,
+            expires_at: self.expiry(now),
+        };
+        self.store.insert(&session).await.map_err(internal)?;
+        info!("Session {} started", session.id);
+        Ok(session)
+    }
+
+    /// Live sessions `owner` may use, most recently used first
+    pub async fn sessions(&self, owner: Option<&ChainOwner>) -> ServerResult<Vec<Session>> {
+        let now = Utc::now();
+        self.store.delete_expired(now).await.map_err(internal)?;
+        let sessions = self.store.sessions().await.map_err(internal)?;
+        Ok(sessions.into_iter().filter(|session| session.permits(owner)).collect())
+    }
+
+    /// A live session `owner` may use
+    pub async fn session(&self, id: &str, owner: Option<&ChainOwner>) -> ServerResult<Session> {
+        let session = self.live(id).await?;
+        if !session.permits(owner) {
+            return Err(ServerError::NotFound(format!("No session {}", id)));
+        }
+        Ok(session)
+    }
+
+    /// A session that has not expired, whoever it belongs to. Sessions found
+    /// expired are deleted.
+    async fn live(&self, id: &str) -> ServerResult<Session> {
+        match self.store.session(id).await.map_err(internal)? {
+            Some(session) if session.expires_at <= Utc::now() => {
+                self.store.delete(id).await.map_err(internal)?;
+                Err(ServerError::NotFound(format!("Session {} expired", id)))
+            }
+            Some(session) => Ok(session),
+            None => Err(ServerError::NotFound(format!("No session {}", id))),
+        }
+    }
+
+    /// A session with its kept turns and the context its next chain gets
+    pub async fn view(&self, id: &str, owner: Option<&ChainOwner>) -> ServerResult<SessionView> {
+        let session = self.session(id, owner).await?;
+        let history = self.store.turns(id).await.map_err(internal)?;
+        let context = self.render(&session.summary, &history);
+        let context_tokens = self.tokenizer.count(&context);
+        Ok(SessionView { session, history, context, context_tokens })
+    }
+
+    /// Forget a session's summary and turns, keeping the session
+    pub async fn reset(&self, id: &str, owner: Option<&ChainOwner>) -> ServerResult<Session> {
+        let _turns = self.turns.lock().await;
+        let mut session = self.session(id, owner).await?;
+        self.store.delete_turns(id).await.map_err(internal)?;
+        session.summary.clear();
+        session.turns = 0;
+        session.last_active_at = Utc::now();
+        session.expires_at = self.expiry(session.last_active_at);
+        self.store.update(&session).await.map_err(internal)?;
+        info!("Session {} reset", id);
+        Ok(session)
+    }
+
+    /// Delete a session with its turns
+    pub async fn delete(&self, id: &str, owner: Option<&ChainOwner>) -> ServerResult<()> {
+        let _turns = self.turns.lock().await;
+        self.session(id, owner).await?;
+        self.store.delete(id).await.map_err(internal)?;
+        info!("Session {} deleted", id);
+        Ok(())
+    }
+
+    /// Make `signal`, the root of a chain naming a session, the next turn
+    /// of it: the conversation so far is put in its metadata and the
+    /// session's expiry is pushed back
+    pub async fn prepare(&self, owner: Option<&ChainOwner>, signal: &mut NeuronSignal) -> ServerResult<()> {
+        let Some(id) = signal.metadata.get(SESSION_ID_KEY).cloned() else {
+            return Ok(());
+        };
+        let mut session = self.session(&id, owner).await?;
+        session.last_active_at = Utc::now();
+        session.expires_at = self.expiry(session.last_active_at);
+        self.store.touch(&session).await.map_err(internal)?;
+
+        let history = self.store.turns(&id).await.map_err(internal)?;
+        let context = self.render(&session.summary, &history);
+        if context.is_empty() {
+            signal.metadata.remove(SESSION_CONTEXT_KEY);
+        } else {
+            signal.metadata.insert(SESSION_CONTEXT_KEY.to_string(), context);
+        }
+        Ok(())
+    }
+
+    /// The conversation a prompt shows: the summary, then the latest turns.
+    /// Quoted turns are left out oldest first, then the summary is cut, to
+    /// keep within `max_context_tokens`.
+    pub fn render(&self, summary: &str, history: &[SessionTurn]) -> String {
+        let budget = self.config.max_context_tokens;
+        let summary = if summary.is_empty() { String::new() } else { format!("Summary: {}\n", summary) };
+        let recent = &history[history.len().saturating_sub(self.config.recent_turns)..];
+        let mut quoted: Vec<String> = recent.iter().map(quote).collect();
+        loop {
+            let context = format!("{}{}", summary, quoted.concat());
+            if quoted.is_empty() || self.tokenizer.count(&context) as usize <= budget {
+                return truncate_tokens(self.tokenizer, &context, budget).to_string();
+            }
+            quoted.remove(0);
+        }
+    }
+
+    /// Add a finished chain of a session as its next turn and fold it into
+    /// the summary. Sessions deleted or expired while the chain ran are
+    /// left alone.
+    pub async fn record_turn(&self, id: &str, chain_id: &str, input: &str, output: &str) -> ServerResult<Option<Session>> {
+        let _turns = self.turns.lock().await;
+        let mut session = match self.live(id).await {
+            Ok(session) => session,
+            Err(ServerError::NotFound(message)) => {
+                debug!("Chain {} not added to its session: {}", chain_id, message);
+                return Ok(None);
+            }
+            Err(e) => return Err(e),
+        };
+        let turn = SessionTurn {
+            seq: session.turns + 1,
+            chain_id: chain_id.to_string(),
+            input: input.to_string(),
+            output: output.to_string(),
+            created_at: Utc::now(),
+        };
+        self.store.insert_turn(id, &turn).await.map_err(internal)?;
+
+        // A failed update keeps the old summary; the turn is still quoted
+        // while it is among the latest
+        match self.summarize(&session.summary, &turn).await {
+            Ok(summary) => session.summary = summary,
+            Err(e) => warn!("Failed to update the summary of session {}: {}", id, e),
+        }
+        session.summary = self.compact(id, session.summary).await;
+
+        session.turns = turn.seq;
+        session.last_active_at = turn.created_at;
+        session.expires_at = self.expiry(turn.created_at);
+        self.store.update(&session).await.map_err(internal)?;
+        if let Some(oldest_kept) = turn.seq.checked_sub(self.config.max_turns as u64) {
+            self.store.delete_turns_through(id, oldest_kept).await.map_err(internal)?;
+        }
+        Ok(Some(session))
+    }
+
+    async fn summarize(&self, summary: &str, turn: &SessionTurn) -> Result<String> {
+        let prompt = format!(
+            "You keep the running summary of a conversation between a user and an AI system. \
+             Update the summary with the latest turn, keeping the facts, decisions and open questions \
+             later turns may refer to. Answer with the summary only.\n\n\
+             SUMMARY SO FAR:\n{}\n\nLATEST TURN:\n{}",
+            if summary.is_empty() { "(none)" } else { summary },
+            quote(turn),
+        );
+        Ok(self.summarizer.send_message(&prompt).await?.trim().to_string())
+    }
+
+    /// `summary` within `max_summary_tokens`: condensed by the summary
+    /// model when it grew past them, and cut if it is still too long
+    async fn compact(&self, id: &str, summary: String) -> String {
+        let max_tokens = self.config.max_summary_tokens;
+        if self.tokenizer.count(&summary) as usize <= max_tokens {
+            return summary;
+        }
+        let prompt = format!(
+            "Condense this summary of a conversation to at most {} tokens, keeping the facts, decisions \
+             and open questions that matter most. Answer with the summary only.\n\n{}",
+            max_tokens, summary
+        );
+        let compacted = match self.summarizer.send_message(&prompt).await {
+            Ok(compacted) => {
+                info!("Summary of session {} compacted", id);
+                compacted.trim().to_string()
+            }
+            Err(e) => {
+                warn!("Failed to compact the summary of session {}: {}", id, e);
+                summary
+            }
+        };
+        truncate_tokens(self.tokenizer, &compacted, max_tokens).to_string()
+    }
+}
+
+fn storage_error(e: sqlx::Error) -> Error {
+    Error::Storage(format!("Session store: {}", e))
+}
+
+fn timestamp(millis: i64) -> DateTime<Utc> {
+    Utc.timestamp_millis_opt(millis).single().unwrap_or_default()
+}
+
+macro_rules! session_from_row {
+    ($row:expr) => {
+        Session {
+            id: $row.get("id"),
+            user_id: $row.get("user_id"),
+            org_id: $row.get("org_id"),
+            summary: $row.get("summary"),
+            turns: $row.get::<i64, _>("turns") as u64,
+            created_at: timestamp($row.get("created_at")),
+            last_active_at: timestamp($row.get("last_active_at")),
+            expires_at: timestamp($row.get("expires_at")),
+        }
+    };
+}
+
+const SESSION_COLUMNS: &str = "id, user_id, org_id, summary, turns, created_at, last_active_at, expires_at";
+
+/// The session tables. Both backends take the same SQL; timestamps are
+/// Unix milliseconds. Turns are deleted with their session by the store,
+/// not the database.
+pub struct SessionStore {
+    pool: DatabasePool,
+}
+
+impl SessionStore {
+    pub fn new(pool: DatabasePool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn insert(&self, session: &Session) -> Result<()> {
+        on_pool!(&self.pool, |pool| {
+            sqlx::query(
+                r#"
+                INSERT INTO chat_sessions (id, user_id, org_id, summary, turns, created_at, last_active_at, expires_at)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+                "#,
+            )
+            .bind(&session.id)
+            .bind(&session.user_id)
+            .bind(&session.org_id)
+            .bind(&session.summary)
+            .bind(session.turns as i64)
+            .bind(session.created_at.timestamp_millis())
+            .bind(session.last_active_at.timestamp_millis())
+            .bind(session.expires_at.timestamp_millis())
+            .execute(pool)
+            .await
+            .map_err(storage_error)?;
+        });
+        Ok(())
+    }
+
+    /// Store a session's summary, turn count and expiry
+    pub async fn update(&self, session: &Session) -> Result<()> {
+        on_pool!(&self.pool, |pool| {
+            sqlx::query("UPDATE chat_sessions SET summary = $1, turns = $2, last_active_at = $3, expires_at = $4 WHERE id = $5")
+                .bind(&session.summary)
+                .bind(session.turns as i64)
+                .bind(session.last_active_at.timestamp_millis())
+                .bind(session.expires_at.timestamp_millis())
+                .bind(&session.id)
+                .execute(pool)
+                .await
+                .map_err(storage_error)?;
+        });
+        Ok(())
+    }
+
+    /// Store a session's expiry alone, leaving a summary being updated
+    pub async fn touch(&self, session: &Session) -> Result<()> {
+        on_pool!(&self.pool, |pool| {
+            sqlx::query("UPDATE chat_sessions SET last_active_at = $1, expires_at = $2 WHERE id = $3")
+                .bind(session.last_active_at.timestamp_millis())
+                .bind(session.expires_at.timestamp_millis())
+                .bind(&session.id)
+                .execute(pool)
+                .await
+                .map_err(storage_error)?;
+        });
+        Ok(())
+    }
+
+    pub async fn session(&self, id: &str) -> Result<Option<Session>> {
+        let query = format!("SELECT {} FROM chat_sessions WHERE id = $1", SESSION_COLUMNS);
+        on_pool!(&self.pool, |pool| {
+            let row = sqlx::query(&query).bind(id).fetch_optional(pool).await.map_err(storage_error)?;
+            Ok(row.map(|row| session_from_row!(row)))
+        })
+    }
+
+    /// Every session, most recently used first
+    pub async fn sessions(&self) -> Result<Vec<Session>> {
+        let query = format!("SELECT {} FROM chat_sessions ORDER BY last_active_at DESC", SESSION_COLUMNS);
+        on_pool!(&self.pool, |pool| {
+            let rows = sqlx::query(&query).fetch_all(pool).await.map_err(storage_error)?;
+            Ok(rows.iter().map(|row| session_from_row!(row)).collect())
+        })
+    }
+
+    /// Delete a session and its turns
+    pub async fn delete(&self, id: &str) -> Result<()> {
+        self.delete_turns(id).await?;
+        on_pool!(&self.pool, |pool| {
+            sqlx::query("DELETE FROM chat_sessions WHERE id = $1").bind(id).execute(pool).await.map_err(storage_error)?;
+        });
+        Ok(())
+    }
+
+    /// Delete sessions that expired by `now`, with their turns
+    pub async fn delete_expired(&self, now: DateTime<Utc>) -> Result<u64> {
+        let now = now.timestamp_millis();
+        let deleted = on_pool!(&self.pool, |pool| {
+            sqlx::query("DELETE FROM chat_session_turns WHERE session_id IN (SELECT id FROM chat_sessions WHERE expires_at <= $1)")
+                .bind(now)
+                .execute(pool)
+                .await
+                .map_err(storage_error)?;
+            sqlx::query("DELETE FROM chat_sessions WHERE expires_at <= $1")
+                .bind(now)
+                .execute(pool)
+                .await
+                .map_err(storage_error)?
+                .rows_affected()
+        });
+        Ok(deleted)
+    }
+
+    pub async fn insert_turn(&self, session_id: &str, turn: &SessionTurn) -> Result<()> {
+        on_pool!(&self.pool, |pool| {
+            sqlx::query(
+                r#"
+                INSERT INTO chat_session_turns (session_id, seq, chain_id, input, output, created_at)
+                VALUES ($1, $2, $3, $4, $5, $6)
+                "#,
+            )
+            .bind(session_id)
+            .bind(turn.seq as i64)
+            .bind(&turn.chain_id)
+            .bind(&turn.input)
+            .bind(&turn.output)
+            .bind(turn.created_at.timestamp_millis())
+            .execute(pool)
+            .await
+            .map_err(storage_error)?;
+        });
+        Ok(())
+    }
+
+    /// Kept turns of a session, oldest first
+    pub async fn turns(&self, session_id: &str) -> Result<Vec<SessionTurn>> {
+        let query = "SELECT seq, chain_id, input, output, created_at FROM chat_session_turns WHERE session_id = $1 ORDER BY seq";
+        on_pool!(&self.pool, |pool| {
+            let rows = sqlx::query(query).bind(session_id).fetch_all(pool).await.map_err(storage_error)?;
+            Ok(rows
+                .iter()
+                .map(|row| SessionTurn {
+                    seq: row.get::<i64, _>("seq") as u64,
+                    chain_id: row.get("chain_id"),
+                    input: row.get("input"),
+                    output: row.get("output"),
+                    created_at: timestamp(row.get("created_at")),
+                })
+                .collect())
+        })
+    }
+
+    pub async fn delete_turns(&self, session_id: &str) -> Result<()> {
+        on_pool!(&self.pool, |pool| {
+            sqlx::query("DELETE FROM chat_session_turns WHERE session_id = $1")
+                .bind(session_id)
+                .execute(pool)
+                .await
+                .map_err(storage_error)?;
+        });
+        Ok(())
+    }
+
+    /// Delete the turns of a session up to and including `seq`
+    pub async fn delete_turns_through(&self, session_id: &str, seq: u64) -> Result<()> {
+        on_pool!(&self.pool, |pool| {
+            sqlx::query("DELETE FROM chat_session_turns WHERE session_id = $1 AND seq <= $2")
+                .bind(session_id)
+                .bind(seq as i64)
+                .execute(pool)
+                .await
+                .map_err(storage_error)?;
+        });
+        Ok(())
+    }
+}
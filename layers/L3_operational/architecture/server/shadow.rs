@@ -0,0 +1,555 @@
+//! Shadowing of production signal submissions to a staging server
+//!
+//! With `shadow.enabled`, [`shadow_middleware`] picks a `sample_rate` share
+//! of the submissions to the endpoints turned on under `shadow.endpoints`
+//! and, once production has accepted one, submits it again to the same
+//! endpoint of `shadow.target_url`. The copy's content and metadata pass
+//! through the [`PiiPipeline`], who submitted it is left out, the cost tags
+//! of `shadow.tags` are added, its node budget is capped at
+//! `shadow.node_budget` and `shadow.of` names the production chain.
+//!
+//! Shadows are sent from spawned tasks, at most `max_in_flight` at a time,
+//! each within `timeout_ms`; a sampled submission finding every slot taken
+//! is not shadowed. The production response is handed on unchanged before
+//! its shadow is even built, so a slow or unreachable staging server shows
+//! neither in production latency nor in its responses.
+//! `POST /api/v1/admin/shadow` switches shadowing off and on, and keeps
+//! working while the server is read-only.
+//!
+//! Each shadow is recorded in the `shadow_chains` table of the server
+//! database, from the `006_shadow_chains` migration: the production chain
+//! with the staging chain it started, or why it did not, so quality
+//! comparisons can join the two.
+
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use axum::{
+    body::{to_bytes, Body, Bytes},
+    extract::{Request, State},
+    http::{header, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, TimeZone, Utc};
+use parking_lot::RwLock;
+use rand::Rng;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use sqlx::Row;
+use tokio::sync::Semaphore;
+use tracing::{debug, info, warn};
+
+use hal9_api_types::{ApiResponse, BatchSubmitRequest, BatchSubmitResponse, SubmitSignalRequest, SubmitSignalResponse};
+use hal9_core::{
+    config::{DatabaseConfig, ShadowConfig, ShadowEndpointsConfig},
+    metadata_schema::keys,
+    migration::PiiPipeline,
+    Error, Result,
+};
+
+use crate::cost_tags;
+use crate::database::{on_pool, DatabasePool};
+use crate::fan_out::{budget_of, FAN_OUT_BUDGET_KEY};
+use crate::sessions::{SESSION_CONTEXT_KEY, SESSION_ID_KEY};
+use crate::simulation::SimRng;
+
+/// Metadata key naming the production chain a staging chain shadows
+pub const SHADOW_OF_KEY: &str = keys::shadow::OF;
+
+/// Header carrying the staging API key
+pub const API_KEY_HEADER: &str = "X-API-Key";
+
+/// Larger submissions are not shadowed; the API refuses them anyway
+pub const MAX_SHADOWED_BODY_BYTES: usize = 2 * 1024 * 1024;
+
+/// Metadata naming who submitted a chain, legacy keys included. Staging
+/// charges the shadow to its own API key.
+const IDENTITY_KEYS: [&str; 5] = [keys::auth::USER_ID, keys::auth::ORG_ID, keys::auth::API_KEY_ID, "user_id", "org_id"];
+
+/// A submission endpoint that can be shadowed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ShadowEndpoint {
+    /// `POST /api/v1/signal`
+    Signal,
+    /// `POST /api/v1/signals/batch`
+    SignalsBatch,
+}
+
+impl ShadowEndpoint {
+    pub const ALL: [ShadowEndpoint; 2] = [ShadowEndpoint::Signal, ShadowEndpoint::SignalsBatch];
+
+    pub fn path(&self) -> &'static str {
+        match self {
+            ShadowEndpoint::Signal => "/api/v1/signal",
+            ShadowEndpoint::SignalsBatch => "/api/v1/signals/batch",
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ShadowEndpoint::Signal => "signal",
+            ShadowEndpoint::SignalsBatch => "signals_batch",
+        }
+    }
+
+    fn parse(value: &str) -> Self {
+        match value {
+            "signals_batch" => ShadowEndpoint::SignalsBatch,
+            _ => ShadowEndpoint::Signal,
+        }
+    }
+
+    fn enabled(&self, endpoints: &ShadowEndpointsConfig) -> bool {
+        match self {
+            ShadowEndpoint::Signal => endpoints.signal,
+            ShadowEndpoint::SignalsBatch => endpoints.signals_batch,
+        }
+    }
+}
+
+/// Check the staging server, sampling and limits of enabled shadowing
+pub fn validate(config: &ShadowConfig, database: &DatabaseConfig) -> Result<()> {
+    if !config.enabled {
+        return Ok(());
+    }
+    let invalid = match config.target_url.as_deref() {
+        None => Some("target_url is required".to_string()),
+        Some(url) if !url.starts_with("http://") && !url.starts_with("https://") => {
+            Some(format!("target_url {} must be an http(s) URL", url))
+        }
+        _ if database.url.is_none() => {
+            Some("shadows are recorded in the server database; set database.url".to_string())
+        }
+        _ if !(0.0..=1.0).contains(&config.sample_rate) => Some("sample_rate must be between 0 and 1".to_string()),
+        _ if config.max_in_flight == 0 => Some("max_in_flight must be positive".to_string()),
+        _ if config.timeout_ms == 0 => Some("timeout_ms must be positive".to_string()),
+        _ if config.node_budget == 0 => Some("node_budget must be positive".to_string()),
+        _ => cost_tags::check(&config.tags.clone().into_iter().collect()).err().map(|e| e.to_string()),
+    };
+    match invalid {
+        Some(message) => Err(Error::Config(format!("Invalid shadow config: {}", message))),
+        None => Ok(()),
+    }
+}
+
+/// A production chain and the staging chain its shadow started
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ShadowRecord {
+    pub production_chain_id: String,
+    /// None when staging did not start a chain
+    pub staging_chain_id: Option<String>,
+    pub endpoint: ShadowEndpoint,
+    pub sent_at: DateTime<Utc>,
+    /// Time staging took to answer or fail
+    pub latency_ms: u64,
+    /// Why staging did not start a chain
+    pub error: Option<String>,
+}
+
+fn storage_error(e: sqlx::Error) -> Error {
+    Error::Storage(format!("Shadow store: {}", e))
+}
+
+const RECORD_COLUMNS: &str = "production_chain_id, staging_chain_id, endpoint, sent_at, latency_ms, error";
+
+/// Shadow records in the server database. Both backends take the same SQL;
+/// timestamps are Unix milliseconds.
+pub struct ShadowStore {
+    pool: DatabasePool,
+}
+
+impl ShadowStore {
+    pub fn new(pool: DatabasePool) -> Self {
+        Self { pool }
+    }
+
+    /// Record a shadow. A production chain keeps the first shadow recorded
+    /// for it.
+    pub async fn insert(&self, record: &ShadowRecord) -> Result<()> {
+        on_pool!(&self.pool, |pool| {
+            sqlx::query(
+                r#"
+                INSERT INTO shadow_chains (production_chain_id, staging_chain_id, endpoint, sent_at, latency_ms, error)
+                VALUES ($1, $2, $3, $4, $5, $6)
+                ON CONFLICT (production_chain_id) DO NOTHING
+                "#,
+            )
+            .bind(&record.production_chain_id)
+            .bind(&record.staging_chain_id)
+            .bind(record.endpoint.as_str())
+            .bind(record.sent_at.timestamp_millis())
+            .bind(record.latency_ms as i64)
+            .bind(&record.error)
+            .execute(pool)
+            .await
+            .map_err(storage_error)?;
+        });
+        Ok(())
+    }
+
+    /// The shadow of a production chain, if it was shadowed
+    pub async fn get(&self, production_chain_id: &str) -> Result<Option<ShadowRecord>> {
+        let query = format!("SELECT {} FROM shadow_chains WHERE production_chain_id = $1", RECORD_COLUMNS);
+        on_pool!(&self.pool, |pool| {
+            let row = sqlx::query(&query)
+                .bind(production_chain_id)
+                .fetch_optional(pool)
+                .await
+                .map_err(storage_error)?;
+            Ok(row.map(|row| ShadowRecord {
+                production_chain_id: row.get("production_chain_id"),
+                staging_chain_id: row.get("staging_chain_id"),
+                endpoint: ShadowEndpoint::parse(&row.get::<String, _>("endpoint")),
+                sent_at: Utc.timestamp_millis_opt(row.get("sent_at")).single().unwrap_or_default(),
+                latency_ms: row.get::<i64, _>("latency_ms") as u64,
+                error: row.get("error"),
+            }))
+        })
+    }
+}
+
+/// Shadowing switched off or on over the API
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShadowSwitch {
+    pub active: bool,
+    /// Who switched, when known
+    pub by: Option<String>,
+    pub at: DateTime<Utc>,
+}
+
+/// Shadowing as reported by `GET /api/v1/admin/shadow`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShadowStatus {
+    /// `shadow.enabled` with a target
+    pub enabled: bool,
+    /// Enabled and not switched off
+    pub active: bool,
+    pub target_url: Option<String>,
+    pub sample_rate: f64,
+    /// Endpoints whose submissions are shadowed
+    pub endpoints: Vec<ShadowEndpoint>,
+    pub in_flight: usize,
+    /// Shadows staging started a chain for, since startup
+    pub sent: u64,
+    /// Shadows staging refused, failed or did not answer in time
+    pub failed: u64,
+    /// Sampled submissions not shadowed because every slot was taken
+    pub dropped: u64,
+    pub last_switch: Option<ShadowSwitch>,
+}
+
+/// Sends shadows of sampled submissions to the staging server
+pub struct ShadowMirror {
+    config: ShadowConfig,
+    client: reqwest::Client,
+    pii: PiiPipeline,
+    active: AtomicBool,
+    last_switch: RwLock<Option<ShadowSwitch>>,
+    slots: Arc<Semaphore>,
+    rng: SimRng,
+    store: RwLock<Option<Arc<ShadowStore>>>,
+    sent: AtomicU64,
+    failed: AtomicU64,
+    dropped: AtomicU64,
+}
+
+impl ShadowMirror {
+    /// A mirror sampling with `rng`; nothing is recorded until a store is
+    /// attached
+    pub fn new(config: ShadowConfig, rng: SimRng) -> Self {
+        let timeout = Duration::from_millis(config.timeout_ms);
+        let client = reqwest::Client::builder()
+            .timeout(timeout)
+            .connect_timeout(timeout)
+            .build()
+            .unwrap_or_default();
+        if config.enabled {
+            info!(
+                "Shadowing {:.2}% of submissions to {}",
+                config.sample_rate * 100.0,
+                config.target_url.as_deref().unwrap_or("-")
+            );
+        }
+        Self {
+            slots: Arc::new(Semaphore::new(config.max_in_flight)),
+            config,
+            client,
+            pii: PiiPipeline::default(),
+            active: AtomicBool::new(true),
+            last_switch: RwLock::new(None),
+            rng,
+            store: RwLock::new(None),
+            sent: AtomicU64::new(0),
+            failed: AtomicU64::new(0),
+            dropped: AtomicU64::new(0),
+        }
+    }
+
+    /// Record shadows in `store`
+    pub fn attach_store(&self, store: ShadowStore) {
+        *self.store.write() = Some(Arc::new(store));
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.config.enabled && self.config.target_url.is_some() && self.active.load(Ordering::SeqCst)
+    }
+
+    /// The off switch, and turning shadowing back on after it. Shadows in
+    /// flight finish.
+    pub fn set_active(&self, active: bool, actor: Option<&str>) -> ShadowStatus {
+        self.active.store(active, Ordering::SeqCst);
+        *self.last_switch.write() = Some(ShadowSwitch {
+            active,
+            by: actor.map(str::to_string),
+            at: Utc::now(),
+        });
+        if active {
+            info!("Shadowing switched on (by {})", actor.unwrap_or("unknown"));
+        } else {
+            warn!("Shadowing switched off (by {})", actor.unwrap_or("unknown"));
+        }
+        self.status()
+    }
+
+    pub fn status(&self) -> ShadowStatus {
+        ShadowStatus {
+            enabled: self.config.enabled && self.config.target_url.is_some(),
+            active: self.is_active(),
+            target_url: self.config.target_url.clone(),
+            sample_rate: self.config.sample_rate,
+            endpoints: ShadowEndpoint::ALL
+                .into_iter()
+                .filter(|endpoint| endpoint.enabled(&self.config.endpoints))
+                .collect(),
+            in_flight: self.config.max_in_flight - self.slots.available_permits(),
+            sent: self.sent.load(Ordering::Relaxed),
+            failed: self.failed.load(Ordering::Relaxed),
+            dropped: self.dropped.load(Ordering::Relaxed),
+            last_switch: self.last_switch.read().clone(),
+        }
+    }
+
+    /// The shadow of a production chain, if it was shadowed
+    pub async fn record(&self, production_chain_id: &str) -> Result<Option<ShadowRecord>> {
+        let store = self.store.read().clone();
+        match store {
+            Some(store) => store.get(production_chain_id).await,
+            None => Ok(None),
+        }
+    }
+
+    /// The endpoint of a request to shadow, if it is sampled. Bodies of
+    /// unknown or excessive length are left alone, unread.
+    fn admit(&self, req: &Request) -> Option<ShadowEndpoint> {
+        if req.method() != Method::POST || !self.is_active() {
+            return None;
+        }
+        let endpoint = ShadowEndpoint::ALL
+            .into_iter()
+            .find(|endpoint| endpoint.path() == req.uri().path() && endpoint.enabled(&self.config.endpoints))?;
+        let length = req.headers()
+            .get(header::CONTENT_LENGTH)
+            .and_then(|length| length.to_str().ok())
+            .and_then(|length| length.parse::<usize>().ok())?;
+        if length > MAX_SHADOWED_BODY_BYTES {
+            return None;
+        }
+        (self.rng.clone().gen::<f64>() < self.config.sample_rate).then_some(endpoint)
+    }
+
+    /// Shadow a submission production accepted from a spawned task, if a
+    /// slot is free
+    fn dispatch(self: &Arc<Self>, endpoint: ShadowEndpoint, submission: Bytes, answer: Bytes) {
+        let Ok(slot) = self.slots.clone().try_acquire_owned() else {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+            debug!("Every shadow slot is taken; {} submission not shadowed", endpoint.as_str());
+            return;
+        };
+        let mirror = self.clone();
+        tokio::spawn(async move {
+            let _slot = slot;
+            mirror.shadow(endpoint, &submission, &answer).await;
+        });
+    }
+
+    async fn shadow(&self, endpoint: ShadowEndpoint, submission: &[u8], answer: &[u8]) {
+        let mut accepted = accepted_submissions(endpoint, submission, answer);
+        // Shadows are never shadowed again, and a retried submission
+        // production answered from its idempotency cache was shadowed once
+        accepted.retain(|(_, request)| !request.metadata.contains_key(SHADOW_OF_KEY));
+        let store = self.store.read().clone();
+        if let Some(store) = &store {
+            let mut fresh = Vec::with_capacity(accepted.len());
+            for (chain_id, request) in accepted {
+                if !matches!(store.get(&chain_id).await, Ok(Some(_))) {
+                    fresh.push((chain_id, request));
+                }
+            }
+            accepted = fresh;
+        }
+        if accepted.is_empty() {
+            return;
+        }
+
+        let (chain_ids, requests): (Vec<String>, Vec<SubmitSignalRequest>) = accepted
+            .into_iter()
+            .map(|(chain_id, request)| {
+                let shadow = self.shadow_of(&chain_id, request);
+                (chain_id, shadow)
+            })
+            .unzip();
+        let sent_at = Utc::now();
+        let started = Instant::now();
+        let outcomes = self.send(endpoint, requests).await;
+        let latency_ms = started.elapsed().as_millis() as u64;
+
+        for (index, production_chain_id) in chain_ids.into_iter().enumerate() {
+            let outcome = match &outcomes {
+                Ok(staging) => staging.get(index).cloned().unwrap_or_else(|| Err("Missing from the staging response".to_string())),
+                Err(e) => Err(e.clone()),
+            };
+            let record = ShadowRecord {
+                production_chain_id,
+                staging_chain_id: outcome.as_ref().ok().cloned(),
+                endpoint,
+                sent_at,
+                latency_ms,
+                error: outcome.err(),
+            };
+            match &record.error {
+                None => self.sent.fetch_add(1, Ordering::Relaxed),
+                Some(e) => {
+                    debug!("Shadow of chain {} failed: {}", record.production_chain_id, e);
+                    self.failed.fetch_add(1, Ordering::Relaxed)
+                }
+            };
+            if let Some(store) = &store {
+                if let Err(e) = store.insert(&record).await {
+                    warn!("Failed to record the shadow of chain {}: {}", record.production_chain_id, e);
+                }
+            }
+        }
+    }
+
+    /// The copy of a submission sent to staging
+    fn shadow_of(&self, production_chain_id: &str, mut request: SubmitSignalRequest) -> SubmitSignalRequest {
+        request.content = self.pii.redact(&request.content);
+        request.metadata.retain(|key, _| !IDENTITY_KEYS.contains(&key.as_str()));
+        // Sessions are kept on this server, not staging
+        request.session_id = None;
+        request.metadata.retain(|key, _| ![SESSION_ID_KEY, SESSION_CONTEXT_KEY].contains(&key.as_str()));
+        for value in request.metadata.values_mut() {
+            *value = self.pii.redact(value);
+        }
+        let budget = budget_of(&request.metadata).map_or(self.config.node_budget, |asked| asked.min(self.config.node_budget));
+        request.metadata.insert(FAN_OUT_BUDGET_KEY.to_string(), budget.to_string());
+        request.metadata.insert(SHADOW_OF_KEY.to_string(), production_chain_id.to_string());
+        request.tags.extend(self.config.tags.iter().map(|(key, value)| (key.clone(), value.clone())));
+        request
+    }
+
+    /// Submit shadows to staging, returning each one's staging chain or why
+    /// it has none, in order
+    async fn send(
+        &self,
+        endpoint: ShadowEndpoint,
+        mut requests: Vec<SubmitSignalRequest>,
+    ) -> std::result::Result<Vec<std::result::Result<String, String>>, String> {
+        match endpoint {
+            ShadowEndpoint::Signal => {
+                let request = requests.pop().ok_or("Nothing to shadow")?;
+                let response: SubmitSignalResponse = self.post(endpoint, &request).await?;
+                Ok(vec![Ok(response.chain_id)])
+            }
+            ShadowEndpoint::SignalsBatch => {
+                let count = requests.len();
+                let response: BatchSubmitResponse = self.post(endpoint, &BatchSubmitRequest { signals: requests }).await?;
+                let mut outcomes = vec![Err("Missing from the staging response".to_string()); count];
+                for result in response.results.into_iter().filter(|result| result.index < count) {
+                    outcomes[result.index] = match (result.signal_id, result.error) {
+                        (Some(chain_id), _) => Ok(chain_id),
+                        (None, error) => Err(error.unwrap_or_else(|| "Rejected by staging".to_string())),
+                    };
+                }
+                Ok(outcomes)
+            }
+        }
+    }
+
+    async fn post<T: Serialize, R: DeserializeOwned>(&self, endpoint: ShadowEndpoint, body: &T) -> std::result::Result<R, String> {
+        let base = self.config.target_url.as_deref().ok_or("No shadow target")?;
+        let mut request = self.client.post(format!("{}{}", base.trim_end_matches('/'), endpoint.path())).json(body);
+        if let Some(api_key) = &self.config.api_key {
+            request = request.header(API_KEY_HEADER, api_key.expose());
+        }
+        let response = request.send().await.map_err(|e| e.to_string())?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(format!("Staging answered {}", status));
+        }
+        let response: ApiResponse<R> = response.json().await.map_err(|e| e.to_string())?;
+        match response.data {
+            Some(data) if response.success => Ok(data),
+            _ => Err(response.error.unwrap_or_else(|| "Staging refused the shadow".to_string())),
+        }
+    }
+}
+
+/// The submissions of a request production started a chain for, with
+/// their chain IDs
+fn accepted_submissions(endpoint: ShadowEndpoint, submission: &[u8], answer: &[u8]) -> Vec<(String, SubmitSignalRequest)> {
+    match endpoint {
+        ShadowEndpoint::Signal => {
+            let request = serde_json::from_slice::<SubmitSignalRequest>(submission);
+            let response = serde_json::from_slice::<ApiResponse<SubmitSignalResponse>>(answer);
+            match (request, response.map(|response| response.data)) {
+                (Ok(request), Ok(Some(response))) => vec![(response.chain_id, request)],
+                _ => Vec::new(),
+            }
+        }
+        ShadowEndpoint::SignalsBatch => {
+            let request = serde_json::from_slice::<BatchSubmitRequest>(submission);
+            let response = serde_json::from_slice::<ApiResponse<BatchSubmitResponse>>(answer);
+            match (request, response.map(|response| response.data)) {
+                (Ok(request), Ok(Some(response))) => response
+                    .results
+                    .into_iter()
+                    .filter_map(|result| Some((result.signal_id?, request.signals.get(result.index)?.clone())))
+                    .collect(),
+                _ => Vec::new(),
+            }
+        }
+    }
+}
+
+/// Hand a sampled submission on to production, then shadow it if
+/// production accepted it. The response is passed on as production gave it.
+pub async fn shadow_middleware(State(mirror): State<Arc<ShadowMirror>>, req: Request, next: Next) -> Response {
+    let Some(endpoint) = mirror.admit(&req) else {
+        return next.run(req).await;
+    };
+
+    let (parts, body) = req.into_parts();
+    let submission = match to_bytes(body, MAX_SHADOWED_BODY_BYTES).await {
+        Ok(bytes) => bytes,
+        Err(e) => return (StatusCode::BAD_REQUEST, format!("Failed to read the request body: {}", e)).into_response(),
+    };
+    let response = next.run(Request::from_parts(parts, Body::from(submission.clone()))).await;
+    if !response.status().is_success() {
+        return response;
+    }
+
+    // The production chain IDs are in the response
+    let (parts, body) = response.into_parts();
+    let answer = match to_bytes(body, usize::MAX).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            warn!("Failed to read the {} response to shadow: {}", endpoint.path(), e);
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+    mirror.dispatch(endpoint, submission, answer.clone());
+    Response::from_parts(parts, Body::from(answer))
+}
@@ -0,0 +1,268 @@
+//! Deterministic simulation mode
+//!
+//! With `simulation.enabled`, randomness in the server draws from [`SimRng`]
+//! streams derived from `simulation.seed` instead of the OS: mock Claude
+//! delays and templates, injected faults, retry jitter and emergence
+//! detection. Combined with paused tokio time (timers, the circuit breaker
+//! and Claude timeouts all use tokio's clock) a chain replays identically
+//! from run to run. Faults are injected in front of each neuron's Claude
+//! instance, either at random per `simulation.fault_rates` or scripted with
+//! [`FaultInjector::fail_next`].
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use parking_lot::Mutex;
+use rand::rngs::StdRng;
+use rand::{Rng, RngCore, SeedableRng};
+use tokio::sync::mpsc;
+use tracing::debug;
+
+use hal9_core::config::SimulationConfig;
+use hal9_core::{Error, Result};
+
+use crate::claude::{ClaudeInterface, TokenUsage};
+
+/// Message of errors returned for injected faults
+pub const INJECTED_FAULT: &str = "injected fault";
+
+/// Shared random number generator, seeded in simulation mode
+#[derive(Clone)]
+pub struct SimRng {
+    seed: Option<u64>,
+    rng: Arc<Mutex<StdRng>>,
+}
+
+impl SimRng {
+    /// Generator replaying the same draws for the same seed
+    pub fn seeded(seed: u64) -> Self {
+        Self {
+            seed: Some(seed),
+            rng: Arc::new(Mutex::new(StdRng::seed_from_u64(seed))),
+        }
+    }
+
+    /// Generator seeded from the OS, as used outside simulation
+    pub fn from_entropy() -> Self {
+        Self {
+            seed: None,
+            rng: Arc::new(Mutex::new(StdRng::from_entropy())),
+        }
+    }
+
+    pub fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+
+    /// Independent stream for the component `name`. A seeded stream depends
+    /// only on the seed and the name, so one component drawing more or less
+    /// does not shift the draws of another.
+    pub fn fork(&self, name: &str) -> Self {
+        match self.seed {
+            Some(seed) => Self::seeded(stream_seed(seed, name)),
+            None => Self::from_entropy(),
+        }
+    }
+}
+
+impl Default for SimRng {
+    fn default() -> Self {
+        Self::from_entropy()
+    }
+}
+
+impl fmt::Debug for SimRng {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SimRng").field("seed", &self.seed).finish()
+    }
+}
+
+impl RngCore for SimRng {
+    fn next_u32(&mut self) -> u32 {
+        self.rng.lock().next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.rng.lock().next_u64()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.rng.lock().fill_bytes(dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> std::result::Result<(), rand::Error> {
+        self.rng.lock().try_fill_bytes(dest)
+    }
+}
+
+/// Seed of stream `name`: FNV-1a of the name mixed into the seed, finished
+/// with splitmix64. Stable across platforms and Rust versions, unlike the
+/// std hashers.
+fn stream_seed(seed: u64, name: &str) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in name.bytes() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    let mut z = (seed ^ hash).wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// Decides which Claude calls fail
+pub struct FaultInjector {
+    config: SimulationConfig,
+    /// Remaining scripted failures by neuron ID or layer
+    scripted: Mutex<HashMap<String, u32>>,
+    injected: AtomicU64,
+}
+
+impl FaultInjector {
+    pub fn new(config: SimulationConfig) -> Self {
+        Self {
+            config,
+            scripted: Mutex::new(HashMap::new()),
+            injected: AtomicU64::new(0),
+        }
+    }
+
+    /// Fail the next `count` calls of a neuron, or of every neuron of a
+    /// layer when `target` is a layer such as "L2"
+    pub fn fail_next(&self, target: &str, count: u32) {
+        *self.scripted.lock().entry(target.to_string()).or_default() += count;
+    }
+
+    /// Drop scripted failures not yet consumed
+    pub fn clear(&self) {
+        self.scripted.lock().clear();
+    }
+
+    /// Faults injected so far
+    pub fn injected(&self) -> u64 {
+        self.injected.load(Ordering::Relaxed)
+    }
+
+    /// Whether the call of `neuron_id` on `layer` fails. Scripted failures
+    /// come first; otherwise the layer's fault rate is drawn from `rng`.
+    fn should_fail(&self, neuron_id: &str, layer: &str, rng: &mut SimRng) -> bool {
+        let scripted = {
+            let mut scripted = self.scripted.lock();
+            [neuron_id, layer].into_iter().any(|target| match scripted.get_mut(target) {
+                Some(remaining) if *remaining > 0 => {
+                    *remaining -= 1;
+                    true
+                }
+                _ => false,
+            })
+        };
+
+        let rate = self.config.fault_rate(layer);
+        let fail = scripted || (rate > 0.0 && rng.gen_bool(rate.min(1.0)));
+        if fail {
+            self.injected.fetch_add(1, Ordering::Relaxed);
+        }
+        fail
+    }
+}
+
+/// Claude instance failing calls chosen by a [`FaultInjector`]
+pub struct FaultyClaude {
+    inner: Box<dyn ClaudeInterface>,
+    neuron_id: String,
+    layer: String,
+    faults: Arc<FaultInjector>,
+    rng: SimRng,
+}
+
+#[async_trait]
+impl ClaudeInterface for FaultyClaude {
+    async fn send_message(&self, message: &str) -> Result<String> {
+        if self.faults.should_fail(&self.neuron_id, &self.layer, &mut self.rng.clone()) {
+            debug!("Injecting fault for {} on {}", self.neuron_id, self.layer);
+            return Err(Error::ClaudeApi(INJECTED_FAULT.to_string()));
+        }
+        self.inner.send_message(message).await
+    }
+
+    async fn stream_message(&self, message: &str, partial: &mpsc::UnboundedSender<String>) -> Result<String> {
+        if self.faults.should_fail(&self.neuron_id, &self.layer, &mut self.rng.clone()) {
+            debug!("Injecting fault for {} on {}", self.neuron_id, self.layer);
+            return Err(Error::ClaudeApi(INJECTED_FAULT.to_string()));
+        }
+        self.inner.stream_message(message, partial).await
+    }
+
+    fn system_prompt(&self) -> &str {
+        self.inner.system_prompt()
+    }
+
+    fn last_token_usage(&self) -> Option<TokenUsage> {
+        self.inner.last_token_usage()
+    }
+}
+
+/// Simulation settings and shared state of one server
+#[derive(Clone)]
+pub struct Simulation {
+    config: SimulationConfig,
+    rng: SimRng,
+    faults: Arc<FaultInjector>,
+}
+
+impl Simulation {
+    pub fn new(config: SimulationConfig) -> Self {
+        let rng = if config.enabled {
+            SimRng::seeded(config.seed)
+        } else {
+            SimRng::from_entropy()
+        };
+        Self {
+            faults: Arc::new(FaultInjector::new(config.clone())),
+            config,
+            rng,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.config.enabled
+    }
+
+    /// Seed of the run, when simulation is on
+    pub fn seed(&self) -> Option<u64> {
+        self.rng.seed()
+    }
+
+    /// Random stream for the component `name`, see [`SimRng::fork`]
+    pub fn rng(&self, name: &str) -> SimRng {
+        self.rng.fork(name)
+    }
+
+    pub fn faults(&self) -> &Arc<FaultInjector> {
+        &self.faults
+    }
+
+    /// Put the fault injector in front of a neuron's Claude instance.
+    /// Outside simulation the instance is returned unchanged.
+    pub fn inject_faults(&self, claude: Box<dyn ClaudeInterface>, neuron_id: &str, layer: &str) -> Box<dyn ClaudeInterface> {
+        if !self.config.enabled {
+            return claude;
+        }
+        Box::new(FaultyClaude {
+            inner: claude,
+            neuron_id: neuron_id.to_string(),
+            layer: layer.to_string(),
+            faults: self.faults.clone(),
+            rng: self.rng(&format!("faults.{}", neuron_id)),
+        })
+    }
+}
+
+impl Default for Simulation {
+    fn default() -> Self {
+        Self::new(SimulationConfig::default())
+    }
+}
@@ -0,0 +1,190 @@
+//! Consciousness measurements pushed to the Gentle Singularity tracker
+//!
+//! With `singularity.endpoint` set the server measures its neurons with the
+//! core consciousness monitor every `interval_secs`, keeps the snapshot in
+//! the signal flow history and posts its phi and coherence to the tracker's
+//! `POST {endpoint}/api/measurements`. The tracker maps them onto its
+//! consciousness scale (see `gentle_singularity::measured`) and answers with
+//! the stage the measurement put it in. When that stage differs from the
+//! previous one, forward or back, a `singularity_stage_changed` server
+//! event and a `singularity.stage_changed` webhook announce it.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tracing::{info, warn};
+
+use hal9_core::config::SingularityConfig;
+use hal9_core::consciousness::{ConsciousnessMetrics, ConsciousnessMonitor};
+use hal9_core::hierarchical::intelligence::SignalFlowHistory;
+use hal9_core::{Error, Neuron, Result};
+
+use crate::api::WsMessage;
+use crate::event_stream::EventLog;
+use crate::neuron::NeuronRegistry;
+use crate::webhooks::WebhookManager;
+
+/// Server event broadcast when the tracker's stage changes
+pub const STAGE_CHANGED_EVENT: &str = "singularity_stage_changed";
+
+/// Measurements the monitor keeps; the signal flow history keeps its own
+const MONITOR_HISTORY: usize = 100;
+
+/// The tracker moving from one stage to another
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StageTransition {
+    pub from: String,
+    pub to: String,
+    /// The stage moved back to an earlier one
+    pub regressed: bool,
+    /// Tracker consciousness level after the measurement
+    pub level: f64,
+    pub phi: f64,
+    pub coherence: f64,
+    pub measured_at: DateTime<Utc>,
+}
+
+/// The part of the tracker's answer the server reads
+#[derive(Debug, Deserialize)]
+struct TrackerProgress {
+    level: TrackerLevel,
+    stage: String,
+    previous_stage: String,
+    #[serde(default)]
+    regressed: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct TrackerLevel {
+    value: f64,
+}
+
+/// Check the tracker endpoint and interval
+pub fn validate(config: &SingularityConfig) -> Result<()> {
+    let Some(endpoint) = &config.endpoint else {
+        return Ok(());
+    };
+    let invalid = if !endpoint.starts_with("http://") && !endpoint.starts_with("https://") {
+        Some(format!("endpoint {} must be an http(s) URL", endpoint))
+    } else if config.interval_secs == 0 {
+        Some("interval_secs must be positive".to_string())
+    } else if config.timeout_ms == 0 {
+        Some("timeout_ms must be positive".to_string())
+    } else {
+        None
+    };
+    match invalid {
+        Some(message) => Err(Error::Config(format!("Invalid singularity config: {}", message))),
+        None => Ok(()),
+    }
+}
+
+/// Measures the server and reports to one tracker
+pub struct SingularityBridge {
+    client: reqwest::Client,
+    url: String,
+    monitor: ConsciousnessMonitor,
+}
+
+impl SingularityBridge {
+    /// A bridge to the configured tracker, `None` without an endpoint
+    pub fn new(config: &SingularityConfig, signal_flow: &SignalFlowHistory) -> Result<Option<Self>> {
+        let Some(endpoint) = &config.endpoint else {
+            return Ok(None);
+        };
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_millis(config.timeout_ms))
+            .build()
+            .map_err(|e| Error::Config(format!("Failed to build singularity client: {}", e)))?;
+        let mut monitor = ConsciousnessMonitor::new(MONITOR_HISTORY);
+        if let Some(traffic) = signal_flow.traffic() {
+            monitor = monitor.with_traffic(traffic.clone());
+        }
+        Ok(Some(Self {
+            client,
+            url: format!("{}/api/measurements", endpoint.trim_end_matches('/')),
+            monitor,
+        }))
+    }
+
+    /// Measure the consciousness of the registered neurons
+    pub async fn measure(&self, registry: &NeuronRegistry) -> ConsciousnessMetrics {
+        let neurons: Vec<Arc<dyn Neuron>> = registry
+            .all()
+            .into_iter()
+            .map(|neuron| neuron as Arc<dyn Neuron>)
+            .collect();
+        self.monitor.measure(&neurons).await
+    }
+
+    /// Send a measurement, returning the stage change it caused if any
+    pub async fn push(&self, metrics: &ConsciousnessMetrics) -> Result<Option<StageTransition>> {
+        let body = json!({ "phi": metrics.phi_value, "coherence": metrics.coherence_level });
+        let response = self
+            .client
+            .post(&self.url)
+            .json(&body)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| Error::Network(format!("Singularity tracker {}: {}", self.url, e)))?;
+        let progress: TrackerProgress = response
+            .json()
+            .await
+            .map_err(|e| Error::Network(format!("Singularity tracker {} answered: {}", self.url, e)))?;
+
+        if progress.stage == progress.previous_stage {
+            return Ok(None);
+        }
+        Ok(Some(StageTransition {
+            from: progress.previous_stage,
+            to: progress.stage,
+            regressed: progress.regressed,
+            level: progress.level.value,
+            phi: metrics.phi_value,
+            coherence: metrics.coherence_level,
+            measured_at: metrics.timestamp,
+        }))
+    }
+}
+
+/// Broadcast a stage change as a server event and a webhook
+pub fn announce(transition: &StageTransition, events: &EventLog, webhooks: &Arc<WebhookManager>) {
+    if transition.regressed {
+        warn!("Singularity stage fell back from {} to {}", transition.from, transition.to);
+    } else {
+        info!("Singularity stage moved from {} to {}", transition.from, transition.to);
+    }
+    events.send(WsMessage::ServerEvent {
+        event: STAGE_CHANGED_EVENT.to_string(),
+        details: json!(transition).to_string(),
+    });
+    webhooks.emit_singularity_stage(transition);
+}
+
+/// Measure every `interval`, record the snapshot in `signal_flow` and push
+/// it to the tracker; an unreachable tracker is logged and retried on the
+/// next tick
+pub async fn sync_task(
+    bridge: SingularityBridge,
+    registry: Arc<NeuronRegistry>,
+    signal_flow: Arc<SignalFlowHistory>,
+    events: Arc<EventLog>,
+    webhooks: Arc<WebhookManager>,
+    interval: Duration,
+) {
+    let mut ticker = tokio::time::interval(interval.max(Duration::from_secs(1)));
+    loop {
+        ticker.tick().await;
+        let metrics = bridge.measure(&registry).await;
+        signal_flow.record_consciousness(metrics.clone());
+        match bridge.push(&metrics).await {
+            Ok(Some(transition)) => announce(&transition, &events, &webhooks),
+            Ok(None) => {}
+            Err(e) => warn!("Failed to push consciousness measurement: {}", e),
+        }
+    }
+}
@@ -0,0 +1,357 @@
+//! Latency SLOs, error budgets and burn-rate alerts
+//!
+//! Each SLO in `monitoring.slos` measures either the signals one layer
+//! processes or whole chains. An event is good when it succeeds within the
+//! SLO's `latency_ms`; failures and slow successes spend the error budget,
+//! which is the share of events the objective allows to be bad. Events are
+//! counted per minute and kept for the longest window in use, so windows
+//! are accurate to the minute.
+//!
+//! The burn rate over a window is the bad ratio seen in it divided by the
+//! ratio the objective allows: at 1.0 the budget lasts exactly the SLO
+//! window, at 14.4 a 30 day budget is gone in about two days. Two alerts
+//! watch it, a fast one over `slo_alerts.fast_window_secs` and a slow one
+//! over `slow_window_secs`. Each is sent as a `slo.burn_rate` webhook when
+//! its burn rate reaches the threshold and re-arms once the rate drops
+//! below it again.
+
+use std::collections::{HashSet, VecDeque};
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+use tracing::{error, warn};
+
+use hal9_core::config::{SloAlertConfig, SloConfig};
+use hal9_core::{Error, Layer, Result};
+
+use crate::chain_tracker::{ChainResult, ChainStatus};
+use crate::webhooks::WebhookManager;
+
+/// Width, in seconds, of the buckets events are counted in
+pub const SLOT_SECS: i64 = 60;
+
+/// Which burn-rate alert fired
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BurnSeverity {
+    /// Fast burn over the short window; the budget is going within hours
+    Fast,
+    /// Sustained burn over the long window
+    Slow,
+}
+
+/// Events seen in a window
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WindowCounts {
+    pub total: u64,
+    /// Failed, or slower than the SLO's latency
+    pub bad: u64,
+}
+
+impl WindowCounts {
+    /// Bad ratio over the ratio `objective` allows; 0 without events
+    pub fn burn_rate(&self, objective: f64) -> f64 {
+        if self.total == 0 {
+            return 0.0;
+        }
+        (self.bad as f64 / self.total as f64) / (1.0 - objective)
+    }
+}
+
+/// Compliance and error budget of one SLO
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SloStatus {
+    pub name: String,
+    /// Layer measured; `None` for whole chains
+    pub layer: Option<String>,
+    pub latency_ms: u64,
+    pub objective: f64,
+    pub window_secs: u64,
+    /// Events in the SLO window
+    pub total: u64,
+    pub bad: u64,
+    /// Share of good events in the window; 1.0 without events
+    pub compliance: f64,
+    pub compliant: bool,
+    /// Share of the window's error budget left, from 1.0 down to 0.0
+    pub budget_remaining: f64,
+    pub fast_burn_rate: f64,
+    pub slow_burn_rate: f64,
+    /// When the budget runs out if the slow-window burn rate holds; `None`
+    /// while nothing is burning
+    pub projected_exhaustion: Option<DateTime<Utc>>,
+    /// Burn-rate alert currently firing, the fast one first
+    pub alert: Option<BurnSeverity>,
+}
+
+/// A burn-rate alert that just fired
+#[derive(Debug, Clone, Serialize)]
+pub struct SloAlert {
+    pub slo: String,
+    pub layer: Option<String>,
+    pub severity: BurnSeverity,
+    pub burn_rate: f64,
+    pub threshold: f64,
+    /// Window the burn rate was measured over
+    pub window_secs: u64,
+    pub budget_remaining: f64,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Slot {
+    /// Unix time the slot starts at, a multiple of `SLOT_SECS`
+    start: i64,
+    total: u64,
+    bad: u64,
+}
+
+struct Objective {
+    config: SloConfig,
+    /// Oldest first
+    slots: VecDeque<Slot>,
+    /// Alerts that fired and have not re-armed yet
+    firing: HashSet<BurnSeverity>,
+}
+
+impl Objective {
+    fn record(&mut self, at: DateTime<Utc>, bad: bool) {
+        let start = at.timestamp().div_euclid(SLOT_SECS) * SLOT_SECS;
+        // Events arrive in order, bar the odd chain finishing late
+        let position = self.slots.iter().rposition(|slot| slot.start <= start);
+        let slot = match position {
+            Some(i) if self.slots[i].start == start => &mut self.slots[i],
+            _ => {
+                let i = position.map_or(0, |i| i + 1);
+                self.slots.insert(i, Slot { start, total: 0, bad: 0 });
+                &mut self.slots[i]
+            }
+        };
+        slot.total += 1;
+        slot.bad += bad as u64;
+    }
+
+    /// Drop slots that ended more than `keep_secs` before `now`
+    fn prune(&mut self, now: DateTime<Utc>, keep_secs: u64) {
+        let cutoff = now.timestamp() - keep_secs as i64;
+        while self.slots.front().is_some_and(|slot| slot.start + SLOT_SECS <= cutoff) {
+            self.slots.pop_front();
+        }
+    }
+
+    /// Events in slots overlapping the `window_secs` up to `now`
+    fn counts(&self, now: DateTime<Utc>, window_secs: u64) -> WindowCounts {
+        let now = now.timestamp();
+        let cutoff = now - window_secs as i64;
+        self.slots.iter()
+            .filter(|slot| slot.start + SLOT_SECS > cutoff && slot.start <= now)
+            .fold(WindowCounts::default(), |counts, slot| WindowCounts {
+                total: counts.total + slot.total,
+                bad: counts.bad + slot.bad,
+            })
+    }
+
+    fn status(&self, now: DateTime<Utc>, alerts: &SloAlertConfig) -> SloStatus {
+        let objective = self.config.objective;
+        let window = self.counts(now, self.config.window_secs);
+        let fast_burn_rate = self.counts(now, alerts.fast_window_secs).burn_rate(objective);
+        let slow_burn_rate = self.counts(now, alerts.slow_window_secs).burn_rate(objective);
+
+        let compliance = if window.total == 0 {
+            1.0
+        } else {
+            (window.total - window.bad) as f64 / window.total as f64
+        };
+        let budget_remaining = (1.0 - window.burn_rate(objective)).max(0.0);
+        // At burn rate 1.0 a full budget lasts one window
+        let projected_exhaustion = if budget_remaining <= 0.0 {
+            Some(now)
+        } else if slow_burn_rate > 0.0 {
+            let secs = budget_remaining * self.config.window_secs as f64 / slow_burn_rate;
+            Some(now + chrono::Duration::milliseconds((secs * 1000.0).round() as i64))
+        } else {
+            None
+        };
+        let alert = [BurnSeverity::Fast, BurnSeverity::Slow]
+            .into_iter()
+            .find(|severity| self.firing.contains(severity));
+
+        SloStatus {
+            name: self.config.name.clone(),
+            layer: self.config.layer.clone(),
+            latency_ms: self.config.latency_ms,
+            objective,
+            window_secs: self.config.window_secs,
+            total: window.total,
+            bad: window.bad,
+            compliance,
+            compliant: compliance >= objective,
+            budget_remaining,
+            fast_burn_rate,
+            slow_burn_rate,
+            projected_exhaustion,
+            alert,
+        }
+    }
+}
+
+/// Counts good and bad events against the configured SLOs
+pub struct SloTracker {
+    alerts: SloAlertConfig,
+    objectives: Mutex<Vec<Objective>>,
+    /// Longest window any status or alert reads
+    keep_secs: u64,
+}
+
+impl SloTracker {
+    pub fn new(slos: Vec<SloConfig>, alerts: SloAlertConfig) -> Self {
+        let keep_secs = slos.iter()
+            .map(|slo| slo.window_secs)
+            .chain([alerts.fast_window_secs, alerts.slow_window_secs])
+            .max()
+            .unwrap_or(0);
+        let objectives = slos.into_iter()
+            .map(|config| Objective { config, slots: VecDeque::new(), firing: HashSet::new() })
+            .collect();
+        Self {
+            alerts,
+            objectives: Mutex::new(objectives),
+            keep_secs,
+        }
+    }
+
+    /// Whether any SLO is configured
+    pub fn is_empty(&self) -> bool {
+        self.objectives.lock().is_empty()
+    }
+
+    /// Record a signal a layer processed, or failed to
+    pub fn record_layer(&self, layer: &str, at: DateTime<Utc>, latency: Duration, success: bool) {
+        self.record(Some(layer), at, latency, success);
+    }
+
+    /// Record a finished chain
+    pub fn record_chain(&self, at: DateTime<Utc>, latency: Duration, success: bool) {
+        self.record(None, at, latency, success);
+    }
+
+    /// Record a chain result from the chain tracker; running and cancelled
+    /// chains are not counted
+    pub fn record_chain_result(&self, result: &ChainResult) {
+        let success = match result.status {
+            ChainStatus::Completed => true,
+            ChainStatus::Failed => false,
+            ChainStatus::Running | ChainStatus::Cancelled => return,
+        };
+        let latency = Duration::from_millis(result.duration_ms.unwrap_or(0).max(0) as u64);
+        self.record_chain(result.completed_at.unwrap_or_else(Utc::now), latency, success);
+    }
+
+    fn record(&self, layer: Option<&str>, at: DateTime<Utc>, latency: Duration, success: bool) {
+        let mut objectives = self.objectives.lock();
+        for objective in objectives.iter_mut().filter(|o| o.config.layer.as_deref() == layer) {
+            let bad = !success || latency > Duration::from_millis(objective.config.latency_ms);
+            objective.record(at, bad);
+            objective.prune(at, self.keep_secs);
+        }
+    }
+
+    /// Status of every SLO as of `now`, in configured order
+    pub fn status(&self, now: DateTime<Utc>) -> Vec<SloStatus> {
+        let mut objectives = self.objectives.lock();
+        objectives.iter_mut()
+            .map(|objective| {
+                objective.prune(now, self.keep_secs);
+                objective.status(now, &self.alerts)
+            })
+            .collect()
+    }
+
+    /// Alerts whose burn rate reached their threshold since the last check
+    pub fn check_alerts(&self, now: DateTime<Utc>) -> Vec<SloAlert> {
+        let checks = [
+            (BurnSeverity::Fast, self.alerts.fast_burn_rate, self.alerts.fast_window_secs),
+            (BurnSeverity::Slow, self.alerts.slow_burn_rate, self.alerts.slow_window_secs),
+        ];
+        let mut fired = Vec::new();
+        let mut objectives = self.objectives.lock();
+        for objective in objectives.iter_mut() {
+            objective.prune(now, self.keep_secs);
+            for (severity, threshold, window_secs) in checks {
+                let burn_rate = objective.counts(now, window_secs).burn_rate(objective.config.objective);
+                if burn_rate < threshold {
+                    objective.firing.remove(&severity);
+                    continue;
+                }
+                if objective.firing.insert(severity) {
+                    let status = objective.status(now, &self.alerts);
+                    fired.push(SloAlert {
+                        slo: status.name,
+                        layer: status.layer,
+                        severity,
+                        burn_rate,
+                        threshold,
+                        window_secs,
+                        budget_remaining: status.budget_remaining,
+                    });
+                }
+            }
+        }
+        fired
+    }
+}
+
+/// Reject SLOs that cannot be measured
+pub fn validate_slos(slos: &[SloConfig]) -> Result<()> {
+    let mut names = HashSet::new();
+    for slo in slos {
+        let invalid = |reason: String| Error::Config(format!("monitoring.slos {}: {}", slo.name, reason));
+        if !names.insert(slo.name.as_str()) {
+            return Err(invalid("duplicate name".to_string()));
+        }
+        if !(slo.objective > 0.0 && slo.objective < 1.0) {
+            return Err(invalid(format!("objective must be between 0 and 1, got {}", slo.objective)));
+        }
+        if slo.window_secs == 0 {
+            return Err(invalid("window_secs must be positive".to_string()));
+        }
+        if let Some(layer) = slo.layer.as_deref().filter(|layer| Layer::from_str(layer).is_none()) {
+            return Err(invalid(format!("unknown layer {}", layer)));
+        }
+    }
+    Ok(())
+}
+
+/// Record finished chains against chain SLOs
+pub async fn chain_recorder_task(tracker: Arc<SloTracker>, mut finished: broadcast::Receiver<ChainResult>) {
+    loop {
+        match finished.recv().await {
+            Ok(result) => tracker.record_chain_result(&result),
+            Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                error!("SLO recorder lagged, {} chains not recorded", skipped);
+            }
+            Err(broadcast::error::RecvError::Closed) => break,
+        }
+    }
+}
+
+/// Check burn rates every `interval` and send alerts that fire to webhooks
+pub async fn alert_task(tracker: Arc<SloTracker>, webhooks: Arc<WebhookManager>, interval: Duration) {
+    let mut ticker = tokio::time::interval(interval);
+    loop {
+        ticker.tick().await;
+        for alert in tracker.check_alerts(Utc::now()) {
+            warn!(
+                "SLO {} burning its error budget at {:.1}x over {}s ({:.0}% left)",
+                alert.slo,
+                alert.burn_rate,
+                alert.window_secs,
+                alert.budget_remaining * 100.0
+            );
+            webhooks.emit_slo_alert(&alert);
+        }
+    }
+}
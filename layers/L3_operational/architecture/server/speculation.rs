@@ -0,0 +1,289 @@
+//! Speculative execution of child signals
+//!
+//! A neuron on one of the layers in `speculation.layers` streams its
+//! response. As soon as the partial output holds a complete forward
+//! instruction (see [`ForwardInstruction::parse_partial`]) the children it
+//! names are sent, tagged with their parent's signal ID, instead of waiting
+//! for the rest of the response. Once the parent finishes, the children its
+//! final output forwards as well are confirmed and not forwarded a second
+//! time; the others are cancelled. A speculative child holds back its own
+//! output until its parent settles it, so a cancelled branch never reaches
+//! the chain: one still queued is dropped, and the cost of one already
+//! processed is recorded as wasted. While cancelled children cost more than
+//! `max_wasted_per_hour` over the last hour, no new speculation starts.
+//!
+//! A response asking for approval starts no children once its
+//! `REQUIRES_APPROVAL:` line is read, and any it started before are
+//! cancelled when it finishes, the gate holding all of its forwards.
+
+use std::collections::{HashSet, VecDeque};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use dashmap::DashMap;
+use parking_lot::Mutex;
+use tokio::sync::{mpsc, watch};
+use tokio::task::JoinHandle;
+use tracing::{debug, error, info, warn};
+use uuid::Uuid;
+
+use hal9_core::config::SpeculationConfig;
+use hal9_core::metadata_schema::keys;
+use hal9_core::{Error, Layer, NeuronSignal, Result};
+
+use crate::approvals;
+use crate::chain_tracker::{ChainTracker, PARENT_ID_KEY};
+use crate::claude::{model_pricing, TokenUsage};
+use crate::metrics::Metrics;
+use crate::neuron::{ForwardInstruction, ManagedNeuron};
+
+/// Metadata key naming the parent a speculative child was started from;
+/// removed once the parent confirms the child
+pub const SPECULATIVE_OF_KEY: &str = keys::routing::SPECULATIVE_OF;
+
+/// Window the wasted budget applies to
+const WASTE_WINDOW: Duration = Duration::from_secs(3600);
+
+/// Where a speculative child stands with its parent
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Settlement {
+    /// The parent is still producing its response
+    Pending,
+    /// The parent's final output forwards the child too
+    Confirmed,
+    /// The parent's final output revised or dropped the child, or failed
+    Cancelled,
+}
+
+/// Check that speculation names real layers and a budget
+pub fn validate(config: &SpeculationConfig) -> Result<()> {
+    let invalid = if let Some(layer) = config.layers.iter().find(|layer| Layer::from_str(layer).is_none()) {
+        Some(format!("unknown layer {}", layer))
+    } else if config.max_wasted_per_hour.is_nan() || config.max_wasted_per_hour < 0.0 {
+        Some(format!("max_wasted_per_hour {} must not be negative", config.max_wasted_per_hour))
+    } else {
+        None
+    };
+    match invalid {
+        Some(message) => Err(Error::Config(format!("Invalid speculation config: {}", message))),
+        None => Ok(()),
+    }
+}
+
+/// Speculative children of the server's neurons and what they wasted
+pub struct Speculator {
+    layers: HashSet<String>,
+    max_wasted_per_hour: f64,
+    /// Cost of each cancelled child over the last hour, oldest first
+    wasted: Mutex<VecDeque<(Instant, f64)>>,
+    /// Set while the wasted budget holds speculation off
+    suspended: AtomicBool,
+    /// Settlement of each speculative child until the child reads it
+    settlements: DashMap<Uuid, (Instant, watch::Sender<Settlement>)>,
+    metrics: Option<Arc<Metrics>>,
+}
+
+impl Speculator {
+    /// Speculation for the configured layers, `None` without any
+    pub fn new(config: &SpeculationConfig) -> Option<Self> {
+        if config.layers.is_empty() {
+            return None;
+        }
+        info!("Speculative execution enabled for layers {}", config.layers.join(", "));
+        Some(Self {
+            layers: config.layers.iter().cloned().collect(),
+            max_wasted_per_hour: config.max_wasted_per_hour,
+            wasted: Mutex::new(VecDeque::new()),
+            suspended: AtomicBool::new(false),
+            settlements: DashMap::new(),
+            metrics: None,
+        })
+    }
+
+    /// Set the metrics speculation outcomes and waste are recorded in
+    pub fn set_metrics(&mut self, metrics: Arc<Metrics>) {
+        self.metrics = Some(metrics);
+    }
+
+    /// Whether neurons on `layer` stream their responses to speculate on
+    pub fn applies_to(&self, layer: &str) -> bool {
+        self.layers.contains(layer)
+    }
+
+    /// Dollars cancelled children cost over the last hour
+    pub fn wasted_last_hour(&self) -> f64 {
+        let mut wasted = self.wasted.lock();
+        while wasted.front().is_some_and(|(at, _)| at.elapsed() > WASTE_WINDOW) {
+            wasted.pop_front();
+        }
+        wasted.iter().map(|(_, dollars)| dollars).sum()
+    }
+
+    /// Whether the wasted budget leaves room for new speculation
+    pub fn within_budget(&self) -> bool {
+        let wasted = self.wasted_last_hour();
+        if wasted >= self.max_wasted_per_hour {
+            if !self.suspended.swap(true, Ordering::Relaxed) {
+                warn!(
+                    "Speculation wasted ${:.4} in the last hour, over its ${:.4} budget; suspending it",
+                    wasted, self.max_wasted_per_hour
+                );
+            }
+            return false;
+        }
+        if self.suspended.swap(false, Ordering::Relaxed) {
+            info!("Speculation waste is back under budget; resuming it");
+        }
+        true
+    }
+
+    /// Read `parent`'s response from `partial` as `neuron` produces it, and
+    /// send the children of its forward instruction once it is complete.
+    /// The task ends with the children it sent.
+    pub fn watch(
+        self: &Arc<Self>,
+        neuron: Arc<ManagedNeuron>,
+        parent: NeuronSignal,
+        mut partial: mpsc::UnboundedReceiver<String>,
+        chain_tracker: Arc<ChainTracker>,
+        signal_tx: mpsc::Sender<NeuronSignal>,
+    ) -> JoinHandle<Vec<NeuronSignal>> {
+        let speculator = self.clone();
+        tokio::spawn(async move {
+            let mut output = String::new();
+            while let Some(piece) = partial.recv().await {
+                output.push_str(&piece);
+                let Some(instruction) = ForwardInstruction::parse_partial(&output) else {
+                    continue;
+                };
+                // What a response asks approval for must not start early
+                if approvals::requested(&output).is_some() {
+                    return Vec::new();
+                }
+                if !speculator.within_budget() {
+                    return Vec::new();
+                }
+                return speculator.launch(&neuron, &parent, &instruction, &chain_tracker, &signal_tx).await;
+            }
+            Vec::new()
+        })
+    }
+
+    /// Send the children of `instruction` ahead of `parent`'s step
+    async fn launch(
+        &self,
+        neuron: &ManagedNeuron,
+        parent: &NeuronSignal,
+        instruction: &ForwardInstruction,
+        chain_tracker: &ChainTracker,
+        signal_tx: &mpsc::Sender<NeuronSignal>,
+    ) -> Vec<NeuronSignal> {
+        self.settlements.retain(|_, (started, _)| started.elapsed() < WASTE_WINDOW);
+
+        let mut children = neuron.forward_signals(instruction);
+        for child in &mut children {
+            child.metadata.insert(PARENT_ID_KEY.to_string(), parent.signal_id.to_string());
+            for (key, value) in &parent.metadata {
+                child.metadata.entry(key.clone()).or_insert_with(|| value.clone());
+            }
+            child.metadata.insert(SPECULATIVE_OF_KEY.to_string(), parent.signal_id.to_string());
+        }
+
+        let mut sent = Vec::with_capacity(children.len());
+        for child in children {
+            let (settlement, _) = watch::channel(Settlement::Pending);
+            self.settlements.insert(child.signal_id, (Instant::now(), settlement));
+            chain_tracker.speculate(&child);
+            debug!(
+                event = "speculation_started",
+                "Starting {} speculatively from the partial output of {}", child.to_neuron, parent.to_neuron
+            );
+            if let Err(e) = signal_tx.send(child.clone()).await {
+                error!("Failed to queue speculative signal: {}", e);
+                self.settlements.remove(&child.signal_id);
+                chain_tracker.withdraw(&child);
+                continue;
+            }
+            if let Some(metrics) = &self.metrics {
+                metrics.record_speculation("started");
+            }
+            sent.push(child);
+        }
+        sent
+    }
+
+    /// Settle `parent`'s speculative children against the children of its
+    /// final output. Those forwarded again, to the same neuron with the
+    /// same content, are confirmed and taken out of `final_children`; the
+    /// rest are cancelled. Returns the confirmed ones.
+    pub fn settle(&self, speculative: Vec<NeuronSignal>, final_children: &mut Vec<NeuronSignal>) -> Vec<NeuronSignal> {
+        let mut confirmed = Vec::new();
+        for child in speculative {
+            let revised = final_children.iter().position(|candidate| {
+                candidate.propagation_type == child.propagation_type
+                    && candidate.to_neuron == child.to_neuron
+                    && candidate.payload.activation.content == child.payload.activation.content
+            });
+            let settlement = match revised {
+                Some(index) => {
+                    final_children.remove(index);
+                    Settlement::Confirmed
+                }
+                None => Settlement::Cancelled,
+            };
+            if let Some(entry) = self.settlements.get(&child.signal_id) {
+                entry.1.send_replace(settlement);
+            }
+            if let Some(metrics) = &self.metrics {
+                metrics.record_speculation(match settlement {
+                    Settlement::Confirmed => "confirmed",
+                    _ => "cancelled",
+                });
+            }
+            if settlement == Settlement::Confirmed {
+                confirmed.push(child);
+            } else {
+                debug!(event = "speculation_cancelled", "Parent output no longer forwards {} to {}", child.signal_id, child.to_neuron);
+            }
+        }
+        confirmed
+    }
+
+    /// How `signal` stands right now; signals that are not speculative,
+    /// or whose settlement was read already, count as confirmed
+    pub fn settlement(&self, signal_id: &Uuid) -> Settlement {
+        self.settlements
+            .get(signal_id)
+            .map(|entry| *entry.1.borrow())
+            .unwrap_or(Settlement::Confirmed)
+    }
+
+    /// Wait for the parent of `signal` to settle it, then forget it
+    pub async fn settled(&self, signal_id: &Uuid) -> Settlement {
+        let receiver = self.settlements.get(signal_id).map(|entry| entry.1.subscribe());
+        let settlement = match receiver {
+            Some(mut receiver) => match receiver.wait_for(|settlement| *settlement != Settlement::Pending).await {
+                Ok(settlement) => *settlement,
+                Err(_) => Settlement::Cancelled,
+            },
+            None => Settlement::Confirmed,
+        };
+        self.settlements.remove(signal_id);
+        settlement
+    }
+
+    /// Record what a cancelled child cost, against the wasted budget
+    pub fn record_wasted(&self, usage: Option<&TokenUsage>) {
+        let Some(usage) = usage else {
+            return;
+        };
+        let (per_1k_prompt, per_1k_completion) = model_pricing(usage.model.as_deref().unwrap_or_default());
+        let dollars = usage.prompt_tokens as f64 / 1000.0 * per_1k_prompt
+            + usage.completion_tokens as f64 / 1000.0 * per_1k_completion;
+        self.wasted.lock().push_back((Instant::now(), dollars));
+        if let Some(metrics) = &self.metrics {
+            metrics.record_speculation_wasted(dollars);
+        }
+    }
+}
@@ -0,0 +1,610 @@
+//! Chain templates: named request shapes submitted with just their variables
+//!
+//! A template targets an entry neuron with content in which `{{name}}`
+//! stands for the value of a declared variable, and carries the cost tags,
+//! priority, deadline and output format its chains are submitted with.
+//! Adding a template under a name already taken adds a version of it;
+//! submissions render the latest version unless they pin one. While
+//! authentication is on, only system admins add versions, and a template
+//! listing `allowed_roles` is only submitted by users of those roles.
+//!
+//! A file variable takes the text of a file. Files of at most
+//! `templates.inline_file_bytes` are inlined into the content; larger ones
+//! are stored as artifacts, served at `GET /api/v1/artifacts/{id}`, and the
+//! content links to them.
+//!
+//! Templates and artifacts are kept in the `chain_templates` and
+//! `template_artifacts` tables of the server database, from the
+//! `007_chain_templates` migration. Artifacts record the organization that
+//! submitted them (`009_artifact_orgs`), whose storage they are billed to.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use chrono::{DateTime, Duration, SecondsFormat, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::Row;
+use tracing::info;
+
+use hal9_api_types::{OutputFormat, SubmitSignalRequest, TemplateValue};
+use hal9_core::{
+    config::{DatabaseConfig, TemplatesConfig},
+    metadata_schema::keys,
+    Error, NeuronConfig, Result, ServerConfig,
+};
+
+use crate::cost_tags::{self, CostTags};
+use crate::database::{on_pool, DatabasePool};
+use crate::database_migrations;
+use crate::error::{ServerError, ServerResult};
+use crate::output_format::FormatRequest;
+
+/// Longest template name; names are used as a path segment
+pub const MAX_NAME_LEN: usize = 64;
+
+/// Check the template settings
+pub fn validate(config: &TemplatesConfig, database: &DatabaseConfig) -> Result<()> {
+    let invalid = |message: &str| Err(Error::Config(format!("Invalid templates config: {}", message)));
+    if config.enabled && database.url.is_none() {
+        return invalid("templates are kept in the server database; set database.url");
+    }
+    if config.max_file_bytes == 0 {
+        return invalid("max_file_bytes must be positive");
+    }
+    if config.inline_file_bytes > config.max_file_bytes {
+        return invalid("inline_file_bytes must not exceed max_file_bytes");
+    }
+    Ok(())
+}
+
+/// Type of a template variable
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum VariableType {
+    String,
+    /// One of `values`
+    Enum { values: Vec<String> },
+    /// The text of a file, inlined or linked as an artifact by its size
+    File,
+}
+
+/// A variable of a template's content
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TemplateVariable {
+    pub name: String,
+    #[serde(flatten)]
+    pub kind: VariableType,
+    /// Value used when a submission gives none; variables without one must
+    /// be given. File variables take none.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+/// What a template renders and how its chains are submitted
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TemplateSpec {
+    /// Entry neuron chains are submitted to
+    pub neuron: String,
+    /// Content with a `{{variable}}` placeholder for each variable used
+    pub content: String,
+    #[serde(default)]
+    pub variables: Vec<TemplateVariable>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Cost attribution tags every chain is submitted with
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub tags: CostTags,
+    /// Transport priority of the root signal, e.g. `high`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority: Option<String>,
+    /// Seconds after submission by which the chain should be processed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deadline_secs: Option<u64>,
+    /// Format the chain's final output is requested in
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_format: Option<OutputFormat>,
+    /// JSON Schema a `json` output is validated against
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_schema: Option<Value>,
+    /// Roles that may submit the template; any when empty
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allowed_roles: Vec<String>,
+}
+
+/// A template to add, or a new version of one
+#[derive(Debug, Clone, Deserialize)]
+pub struct NewTemplate {
+    pub name: String,
+    #[serde(flatten)]
+    pub spec: TemplateSpec,
+}
+
+/// A version of a named template
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChainTemplate {
+    pub name: String,
+    /// Versions of a name count up from 1
+    pub version: u32,
+    #[serde(flatten)]
+    pub spec: TemplateSpec,
+    /// User who added the version, when known
+    pub created_by: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl ChainTemplate {
+    /// Whether a user of `role`, or a caller who is not authenticated, may
+    /// submit the template
+    pub fn permits(&self, role: Option<&str>) -> bool {
+        let allowed = &self.spec.allowed_roles;
+        allowed.is_empty() || role.is_some_and(|role| allowed.iter().any(|allowed| allowed == role))
+    }
+}
+
+/// A file given for a file variable, stored because it was too large to
+/// inline
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Artifact {
+    pub id: String,
+    /// Template and version the file was submitted to
+    pub template: String,
+    pub version: u32,
+    /// File name as given
+    pub name: String,
+    pub content: String,
+    pub size_bytes: u64,
+    /// Organization of the submitter, if any
+    pub org_id: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A template rendered with a submission's values
+#[derive(Debug, Clone)]
+pub struct RenderedTemplate {
+    /// The submission of the chain
+    pub request: SubmitSignalRequest,
+    /// Artifacts stored for files too large to inline, in variable order
+    pub artifacts: Vec<String>,
+}
+
+/// Names of the placeholders of `content`, in order
+fn placeholders(content: &str) -> std::result::Result<Vec<&str>, String> {
+    let mut names = Vec::new();
+    let mut rest = content;
+    while let Some(open) = rest.find("{{") {
+        let after = &rest[open + 2..];
+        let close = after.find("}}").ok_or_else(|| "Content has an unclosed placeholder".to_string())?;
+        names.push(after[..close].trim());
+        rest = &after[close + 2..];
+    }
+    Ok(names)
+}
+
+/// `content` with each placeholder replaced by its value. Values are not
+/// scanned for placeholders themselves.
+fn substitute(content: &str, values: &HashMap<&str, String>) -> String {
+    let mut output = String::with_capacity(content.len());
+    let mut rest = content;
+    while let Some(open) = rest.find("{{") {
+        let after = &rest[open + 2..];
+        let Some(close) = after.find("}}") else { break };
+        output.push_str(&rest[..open]);
+        output.push_str(values.get(after[..close].trim()).map_or("", String::as_str));
+        rest = &after[close + 2..];
+    }
+    output.push_str(rest);
+    output
+}
+
+fn valid_name(name: &str) -> bool {
+    !name.is_empty() && name.len() <= MAX_NAME_LEN && name.bytes().all(|b| b.is_ascii_alphanumeric() || b"-_".contains(&b))
+}
+
+/// Why `template` cannot be added, if it cannot
+fn check_template(template: &NewTemplate, neurons: &[NeuronConfig]) -> std::result::Result<(), String> {
+    let spec = &template.spec;
+    if !valid_name(&template.name) {
+        return Err(format!(
+            "Template name '{}' must be 1 to {} letters, digits, '-' or '_'",
+            template.name, MAX_NAME_LEN
+        ));
+    }
+    if !neurons.iter().any(|neuron| neuron.id == spec.neuron) {
+        return Err(format!("No neuron {}", spec.neuron));
+    }
+    if spec.content.trim().is_empty() {
+        return Err("A template needs content".to_string());
+    }
+
+    let mut declared = HashSet::new();
+    for variable in &spec.variables {
+        if !valid_name(&variable.name) {
+            return Err(format!("Variable name '{}' must be letters, digits, '-' or '_'", variable.name));
+        }
+        if !declared.insert(variable.name.as_str()) {
+            return Err(format!("Variable {} is declared twice", variable.name));
+        }
+        match (&variable.kind, &variable.default) {
+            (VariableType::Enum { values }, _) if values.is_empty() => {
+                return Err(format!("Enum variable {} has no values", variable.name));
+            }
+            (VariableType::Enum { values }, Some(default)) if !values.contains(default) => {
+                return Err(format!("Default of {} is not one of its values", variable.name));
+            }
+            (VariableType::File, Some(_)) => {
+                return Err(format!("File variable {} takes no default", variable.name));
+            }
+            _ => {}
+        }
+    }
+    for name in placeholders(&spec.content)? {
+        if !declared.contains(name) {
+            return Err(format!("Content uses {{{{{}}}}}, which is not a declared variable", name));
+        }
+    }
+
+    cost_tags::check(&spec.tags).map_err(|e| e.to_string())?;
+    if spec.priority.as_deref().is_some_and(|priority| priority.trim().is_empty()) {
+        return Err("Priority must not be empty".to_string());
+    }
+    if spec.deadline_secs == Some(0) {
+        return Err("deadline_secs must be positive".to_string());
+    }
+    match spec.output_format {
+        Some(format) => {
+            FormatRequest::new(format, spec.output_schema.clone()).map_err(|e| e.to_string())?;
+        }
+        None if spec.output_schema.is_some() => return Err("An output schema needs the json output format".to_string()),
+        None => {}
+    }
+    if spec.allowed_roles.iter().any(|role| role.trim().is_empty()) {
+        return Err("Allowed roles must not be empty".to_string());
+    }
+    Ok(())
+}
+
+fn internal(e: Error) -> ServerError {
+    ServerError::Internal(e.to_string())
+}
+
+/// Templates and the artifacts submitted to them
+pub struct TemplateLibrary {
+    config: TemplatesConfig,
+    store: TemplateStore,
+}
+
+impl TemplateLibrary {
+    /// Connect to the server database if templates are enabled; the
+    /// template tables come with its migrations
+    pub async fn open(config: &ServerConfig) -> Result<Option<Self>> {
+        if !config.templates.enabled {
+            return Ok(None);
+        }
+        let pool = database_migrations::connect(&config.database)
+            .await?
+            .ok_or_else(|| Error::Config("Chain templates need database.url".to_string()))?;
+        info!("Chain templates kept in the {:?} database", pool.database_type());
+        Ok(Some(Self::new(config.templates.clone(), TemplateStore::new(pool))))
+    }
+
+    pub fn new(config: TemplatesConfig, store: TemplateStore) -> Self {
+        Self { config, store }
+    }
+
+    /// Add a template targeting one of `neurons`, as a new version if the
+    /// name is taken
+    pub async fn add(&self, new: NewTemplate, neurons: &[NeuronConfig], created_by: Option<&str>) -> ServerResult<ChainTemplate> {
+        check_template(&new, neurons).map_err(ServerError::InvalidInput)?;
+        let latest = self.store.latest_version(&new.name).await.map_err(internal)?;
+        let template = ChainTemplate {
+            name: new.name,
+            version: latest.unwrap_or(0) + 1,
+            spec: new.spec,
+            created_by: created_by.map(str::to_string),
+            created_at: Utc::now(),
+        };
+        self.store.insert_template(&template).await.map_err(internal)?;
+        info!("Chain template {} v{} added for neuron {}", template.name, template.version, template.spec.neuron);
+        Ok(template)
+    }
+
+    /// The latest version of every template, by name
+    pub async fn templates(&self) -> ServerResult<Vec<ChainTemplate>> {
+        self.store.latest_templates().await.map_err(internal)
+    }
+
+    /// Every version of a template, oldest first
+    pub async fn versions(&self, name: &str) -> ServerResult<Vec<ChainTemplate>> {
+        let versions = self.store.versions(name).await.map_err(internal)?;
+        if versions.is_empty() {
+            return Err(ServerError::NotFound(format!("No template {}", name)));
+        }
+        Ok(versions)
+    }
+
+    /// A version of a template, the latest if `version` is `None`
+    pub async fn template(&self, name: &str, version: Option<u32>) -> ServerResult<ChainTemplate> {
+        let version = match version {
+            Some(version) => Some(version),
+            None => self.store.latest_version(name).await.map_err(internal)?,
+        };
+        let template = match version {
+            Some(version) => self.store.template(name, version).await.map_err(internal)?,
+            None => None,
+        };
+        template.ok_or_else(|| match version {
+            Some(version) => ServerError::NotFound(format!("No version {} of template {}", version, name)),
+            None => ServerError::NotFound(format!("No template {}", name)),
+        })
+    }
+
+    /// Render `template` with `values` into the submission of a chain to
+    /// its neuron, one of `neurons`. Every invalid value is reported at
+    /// once; artifacts are only stored once all are valid, under `org_id`.
+    pub async fn render(
+        &self,
+        template: &ChainTemplate,
+        values: HashMap<String, TemplateValue>,
+        neurons: &[NeuronConfig],
+        org_id: Option<&str>,
+    ) -> ServerResult<RenderedTemplate> {
+        let spec = &template.spec;
+        let neuron = neurons.iter().find(|neuron| neuron.id == spec.neuron).ok_or_else(|| {
+            ServerError::InvalidInput(format!("Template {} targets neuron {}, which is not running", template.name, spec.neuron))
+        })?;
+
+        let mut errors: Vec<String> = values
+            .keys()
+            .filter(|name| !spec.variables.iter().any(|variable| &variable.name == *name))
+            .map(|name| format!("{} is not a variable of the template", name))
+            .collect();
+        errors.sort();
+        let mut rendered: HashMap<&str, String> = HashMap::new();
+        let mut files = Vec::new();
+        for variable in &spec.variables {
+            let name = variable.name.as_str();
+            match (&variable.kind, values.get(name)) {
+                (_, None) => match &variable.default {
+                    Some(default) => {
+                        rendered.insert(name, default.clone());
+                    }
+                    None => errors.push(format!("{} is required", name)),
+                },
+                (VariableType::File, Some(TemplateValue::Text(_))) => {
+                    errors.push(format!("{} takes a file", name));
+                }
+                (VariableType::String | VariableType::Enum { .. }, Some(TemplateValue::File { .. })) => {
+                    errors.push(format!("{} takes text, not a file", name));
+                }
+                (VariableType::File, Some(TemplateValue::File { file, content })) => {
+                    if content.len() > self.config.max_file_bytes {
+                        errors.push(format!(
+                            "{} is a file of {} bytes, over the limit of {}",
+                            name,
+                            content.len(),
+                            self.config.max_file_bytes
+                        ));
+                    } else {
+                        files.push((name, file, content));
+                    }
+                }
+                (VariableType::Enum { values }, Some(TemplateValue::Text(value))) if !values.contains(value) => {
+                    errors.push(format!("{} must be one of {}, not '{}'", name, values.join(", "), value));
+                }
+                (_, Some(TemplateValue::Text(value))) => {
+                    rendered.insert(name, value.clone());
+                }
+            }
+        }
+        if !errors.is_empty() {
+            return Err(ServerError::InvalidInput(format!(
+                "Invalid variables for template {} v{}: {}",
+                template.name,
+                template.version,
+                errors.join("; ")
+            )));
+        }
+
+        let mut artifacts = Vec::new();
+        for (name, file, content) in files {
+            if content.len() <= self.config.inline_file_bytes {
+                rendered.insert(name, content.clone());
+                continue;
+            }
+            let artifact = Artifact {
+                id: uuid::Uuid::new_v4().to_string(),
+                template: template.name.clone(),
+                version: template.version,
+                name: file.clone(),
+                content: content.clone(),
+                size_bytes: content.len() as u64,
+                org_id: org_id.map(str::to_string),
+                created_at: Utc::now(),
+            };
+            self.store.insert_artifact(&artifact).await.map_err(internal)?;
+            rendered.insert(
+                name,
+                format!(
+                    "[File {} of {} bytes, stored as artifact {}: GET /api/v1/artifacts/{}]",
+                    artifact.name, artifact.size_bytes, artifact.id, artifact.id
+                ),
+            );
+            artifacts.push(artifact.id);
+        }
+
+        let mut request = SubmitSignalRequest::new(neuron.layer.clone(), substitute(&spec.content, &rendered));
+        request.neuron_id = Some(neuron.id.clone());
+        request.output_format = spec.output_format;
+        request.output_schema = spec.output_schema.clone();
+        request.tags = spec.tags.clone();
+        request.metadata.insert(keys::template::NAME.to_string(), template.name.clone());
+        request.metadata.insert(keys::template::VERSION.to_string(), template.version.to_string());
+        if let Some(priority) = &spec.priority {
+            request.metadata.insert(keys::network::PRIORITY.to_string(), priority.clone());
+        }
+        if let Some(secs) = spec.deadline_secs {
+            let deadline = Utc::now() + Duration::seconds(secs.min(i64::MAX as u64) as i64);
+            request.metadata.insert(keys::routing::DEADLINE.to_string(), deadline.to_rfc3339_opts(SecondsFormat::Secs, true));
+        }
+        Ok(RenderedTemplate { request, artifacts })
+    }
+
+    pub async fn artifact(&self, id: &str) -> ServerResult<Artifact> {
+        self.store
+            .artifact(id)
+            .await
+            .map_err(internal)?
+            .ok_or_else(|| ServerError::NotFound(format!("No artifact {}", id)))
+    }
+
+    /// Bytes of artifacts stored per organization
+    pub async fn artifact_bytes(&self) -> Result<HashMap<String, u64>> {
+        self.store.artifact_bytes().await
+    }
+}
+
+fn storage_error(e: sqlx::Error) -> Error {
+    Error::Storage(format!("Template store: {}", e))
+}
+
+fn timestamp(millis: i64) -> DateTime<Utc> {
+    Utc.timestamp_millis_opt(millis).single().unwrap_or_default()
+}
+
+macro_rules! template_from_row {
+    ($row:expr) => {
+        ChainTemplate {
+            name: $row.get("name"),
+            version: $row.get::<i64, _>("version") as u32,
+            spec: serde_json::from_str(&$row.get::<String, _>("spec"))?,
+            created_by: $row.get("created_by"),
+            created_at: timestamp($row.get("created_at")),
+        }
+    };
+}
+
+/// The template tables. Both backends take the same SQL; timestamps are
+/// Unix milliseconds.
+pub struct TemplateStore {
+    pool: DatabasePool,
+}
+
+impl TemplateStore {
+    pub fn new(pool: DatabasePool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn insert_template(&self, template: &ChainTemplate) -> Result<()> {
+        let spec = serde_json::to_string(&template.spec)?;
+        on_pool!(&self.pool, |pool| {
+            sqlx::query("INSERT INTO chain_templates (name, version, spec, created_by, created_at) VALUES ($1, $2, $3, $4, $5)")
+                .bind(&template.name)
+                .bind(template.version as i64)
+                .bind(&spec)
+                .bind(&template.created_by)
+                .bind(template.created_at.timestamp_millis())
+                .execute(pool)
+                .await
+                .map_err(storage_error)?;
+        });
+        Ok(())
+    }
+
+    pub async fn latest_version(&self, name: &str) -> Result<Option<u32>> {
+        let version: Option<i64> = on_pool!(&self.pool, |pool| {
+            sqlx::query("SELECT MAX(version) AS version FROM chain_templates WHERE name = $1")
+                .bind(name)
+                .fetch_one(pool)
+                .await
+                .map_err(storage_error)?
+                .get("version")
+        });
+        Ok(version.map(|version| version as u32))
+    }
+
+    pub async fn template(&self, name: &str, version: u32) -> Result<Option<ChainTemplate>> {
+        let query = "SELECT name, version, spec, created_by, created_at FROM chain_templates WHERE name = $1 AND version = $2";
+        on_pool!(&self.pool, |pool| {
+            let row = sqlx::query(query).bind(name).bind(version as i64).fetch_optional(pool).await.map_err(storage_error)?;
+            row.map(|row| -> Result<ChainTemplate> { Ok(template_from_row!(row)) }).transpose()
+        })
+    }
+
+    /// Every version of a template, oldest first
+    pub async fn versions(&self, name: &str) -> Result<Vec<ChainTemplate>> {
+        let query = "SELECT name, version, spec, created_by, created_at FROM chain_templates WHERE name = $1 ORDER BY version";
+        on_pool!(&self.pool, |pool| {
+            let rows = sqlx::query(query).bind(name).fetch_all(pool).await.map_err(storage_error)?;
+            rows.iter().map(|row| -> Result<ChainTemplate> { Ok(template_from_row!(row)) }).collect()
+        })
+    }
+
+    /// The latest version of every template, by name
+    pub async fn latest_templates(&self) -> Result<Vec<ChainTemplate>> {
+        let query = r#"
+            SELECT name, version, spec, created_by, created_at FROM chain_templates t
+            WHERE version = (SELECT MAX(version) FROM chain_templates WHERE name = t.name)
+            ORDER BY name
+        "#;
+        on_pool!(&self.pool, |pool| {
+            let rows = sqlx::query(query).fetch_all(pool).await.map_err(storage_error)?;
+            rows.iter().map(|row| -> Result<ChainTemplate> { Ok(template_from_row!(row)) }).collect()
+        })
+    }
+
+    pub async fn insert_artifact(&self, artifact: &Artifact) -> Result<()> {
+        on_pool!(&self.pool, |pool| {
+            sqlx::query(
+                r#"
+                INSERT INTO template_artifacts (id, template, version, name, content, size_bytes, org_id, created_at)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+                "#,
+            )
+            .bind(&artifact.id)
+            .bind(&artifact.template)
+            .bind(artifact.version as i64)
+            .bind(&artifact.name)
+            .bind(&artifact.content)
+            .bind(artifact.size_bytes as i64)
+            .bind(&artifact.org_id)
+            .bind(artifact.created_at.timestamp_millis())
+            .execute(pool)
+            .await
+            .map_err(storage_error)?;
+        });
+        Ok(())
+    }
+
+    pub async fn artifact(&self, id: &str) -> Result<Option<Artifact>> {
+        let query =
+            "SELECT id, template, version, name, content, size_bytes, org_id, created_at FROM template_artifacts WHERE id = $1";
+        on_pool!(&self.pool, |pool| {
+            let row = sqlx::query(query).bind(id).fetch_optional(pool).await.map_err(storage_error)?;
+            Ok(row.map(|row| Artifact {
+                id: row.get("id"),
+                template: row.get("template"),
+                version: row.get::<i64, _>("version") as u32,
+                name: row.get("name"),
+                content: row.get("content"),
+                size_bytes: row.get::<i64, _>("size_bytes") as u64,
+                org_id: row.get("org_id"),
+                created_at: timestamp(row.get("created_at")),
+            }))
+        })
+    }
+
+    pub async fn artifact_bytes(&self) -> Result<HashMap<String, u64>> {
+        let query = r#"
+            SELECT org_id, CAST(SUM(size_bytes) AS BIGINT) AS bytes
+            FROM template_artifacts
+            WHERE org_id IS NOT NULL
+            GROUP BY org_id
+        "#;
+        on_pool!(&self.pool, |pool| {
+            let rows = sqlx::query(query).fetch_all(pool).await.map_err(storage_error)?;
+            Ok(rows.iter().map(|row| (row.get("org_id"), row.get::<i64, _>("bytes") as u64)).collect())
+        })
+    }
+}
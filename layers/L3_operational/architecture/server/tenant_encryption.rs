@@ -0,0 +1,125 @@
+//! Per-organization encryption of signal payloads and memories at rest
+//!
+//! With `encryption.key_store` set the server opens a [`TenantKeyring`]
+//! there and hands it to the memory store and the signal log, which seal
+//! the content of everything attributed (`auth.org_id`) to an organization
+//! listed in `encryption.orgs`. Admins rotate an organization's key and turn
+//! its encryption on or off at runtime; a background sweep reseals values
+//! still under retired keys so rotated keys stop being needed.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use tracing::{info, warn};
+
+use hal9_core::config::{EncryptedSearch, EncryptionConfig};
+use hal9_core::encryption::TenantKeyring;
+use hal9_core::memory::{EmbeddingGenerator, MemoryStore, SealedSearch};
+use hal9_core::secrets::{EnvKeyProvider, FileKeyProvider, MasterKeyProvider};
+use hal9_core::sqlite::{SqlitePools, SqliteTuning};
+use hal9_core::{Error, Result};
+
+use crate::database_runtime::RuntimeDatabase;
+
+/// Values resealed per query by the sweep
+const SWEEP_BATCH: usize = 500;
+
+/// Check that encryption has a key store and sane search and sweep settings
+pub fn validate(config: &EncryptionConfig) -> Result<()> {
+    let invalid = if config.key_store.is_none() && !config.orgs.is_empty() {
+        Some("orgs need a key_store".to_string())
+    } else if config.key_store.is_none() && config.master_key_file.is_some() {
+        Some("master_key_file needs a key_store".to_string())
+    } else if config.sweep_interval_secs == 0 {
+        Some("sweep_interval_secs must be positive".to_string())
+    } else if !(0.0..=1.0).contains(&config.search_min_similarity) {
+        Some(format!("search_min_similarity {} must be within 0 and 1", config.search_min_similarity))
+    } else {
+        None
+    };
+    match invalid {
+        Some(message) => Err(Error::Config(format!("Invalid encryption config: {}", message))),
+        None => Ok(()),
+    }
+}
+
+/// The keyring of the configured key store, `None` without one
+pub async fn open_keyring(config: &EncryptionConfig) -> Result<Option<Arc<TenantKeyring>>> {
+    let Some(path) = &config.key_store else {
+        return Ok(None);
+    };
+    let pools = SqlitePools::connect(&format!("sqlite:{}?mode=rwc", path), SqliteTuning::default())
+        .await
+        .map_err(|e| Error::Storage(format!("Failed to open key store {}: {}", path, e)))?;
+    let master: Box<dyn MasterKeyProvider> = match &config.master_key_file {
+        Some(file) => Box::new(FileKeyProvider::new(file)),
+        None => Box::new(EnvKeyProvider),
+    };
+    let keyring = TenantKeyring::open(pools, master.as_ref(), config.orgs.iter().cloned()).await?;
+    info!("Encryption at rest enabled for {} organizations", config.orgs.len());
+    Ok(Some(Arc::new(keyring)))
+}
+
+/// How memory content searches match sealed entries
+pub fn sealed_search(config: &EncryptionConfig, embedding_dimension: usize) -> SealedSearch {
+    match config.search {
+        EncryptedSearch::Embedding => SealedSearch::Embedding {
+            generator: EmbeddingGenerator::new(embedding_dimension),
+            min_similarity: config.search_min_similarity,
+        },
+        EncryptedSearch::Disabled => SealedSearch::Disabled,
+    }
+}
+
+/// Reseal memories and logged signals still under retired keys every
+/// `interval`, a batch at a time until none are left
+pub async fn sweep_task(
+    memory: Option<Arc<dyn MemoryStore>>,
+    signal_log: Option<Arc<RuntimeDatabase>>,
+    interval: Duration,
+) {
+    let mut ticker = tokio::time::interval(interval.max(Duration::from_secs(1)));
+    loop {
+        ticker.tick().await;
+        if let Some(memory) = &memory {
+            let mut resealed = 0;
+            loop {
+                match memory.reseal(SWEEP_BATCH).await {
+                    Ok(count) => {
+                        resealed += count;
+                        if count < SWEEP_BATCH as u64 {
+                            break;
+                        }
+                    }
+                    Err(e) => {
+                        warn!("Failed to reseal memories: {}", e);
+                        break;
+                    }
+                }
+            }
+            if resealed > 0 {
+                info!("Resealed {} memories under current keys", resealed);
+            }
+        }
+        if let Some(signal_log) = &signal_log {
+            let mut resealed = 0;
+            loop {
+                match signal_log.reseal_signals(SWEEP_BATCH as i64).await {
+                    Ok(count) => {
+                        resealed += count;
+                        if count < SWEEP_BATCH as u64 {
+                            break;
+                        }
+                    }
+                    Err(e) => {
+                        warn!("Failed to reseal logged signals: {}", e);
+                        break;
+                    }
+                }
+            }
+            if resealed > 0 {
+                info!("Resealed {} logged signals under current keys", resealed);
+            }
+        }
+    }
+}
@@ -0,0 +1,105 @@
+//! Admin endpoints: refused without a token and to callers who are not
+//! system admins
+
+mod common;
+
+use axum::http::StatusCode;
+
+use hal9_server::api::create_api_router;
+use hal9_server::dev::{self, DevOptions};
+
+use common::{request, send};
+
+/// Every admin route, with the method it is served on
+const ADMIN_ROUTES: &[(&str, &str)] = &[
+    ("GET", "/api/v1/logs"),
+    ("GET", "/api/v1/logs/stats"),
+    ("GET", "/api/v1/admin/log-levels"),
+    ("PUT", "/api/v1/admin/log-levels"),
+    ("DELETE", "/api/v1/admin/log-levels/hal9_server"),
+    ("GET", "/api/v1/admin/layers"),
+    ("POST", "/api/v1/admin/layers/L2/pause"),
+    ("POST", "/api/v1/admin/layers/L2/resume"),
+    ("POST", "/api/v1/admin/drain"),
+    ("GET", "/api/v1/admin/config/effective"),
+    ("PUT", "/api/v1/admin/config/overrides"),
+    ("DELETE", "/api/v1/admin/config/overrides/claude.model"),
+    ("GET", "/api/v1/admin/warm-pools"),
+    ("PUT", "/api/v1/admin/warm-pools/wasm"),
+    ("GET", "/api/v1/admin/topology"),
+    ("POST", "/api/v1/admin/topology/patch"),
+    ("POST", "/api/v1/admin/topology/revert"),
+    ("GET", "/api/v1/admin/readonly"),
+    ("POST", "/api/v1/admin/readonly"),
+    ("GET", "/api/v1/admin/retention"),
+    ("POST", "/api/v1/admin/retention/run"),
+    ("POST", "/api/v1/admin/compact"),
+    ("GET", "/api/v1/admin/timeouts"),
+    ("PUT", "/api/v1/admin/timeouts/adaptive"),
+    ("GET", "/api/v1/admin/chaos"),
+    ("POST", "/api/v1/admin/chaos"),
+    ("DELETE", "/api/v1/admin/chaos"),
+    ("DELETE", "/api/v1/admin/chaos/fault-1"),
+    ("GET", "/api/v1/admin/mock/scenarios"),
+    ("PUT", "/api/v1/admin/mock/scenario"),
+    ("POST", "/api/v1/admin/mock/recording/save"),
+    ("GET", "/api/v1/admin/encryption"),
+    ("PUT", "/api/v1/admin/orgs/acme/encryption"),
+    ("POST", "/api/v1/admin/orgs/acme/encryption/rotate"),
+    ("GET", "/api/v1/admin/ingestion"),
+    ("GET", "/api/v1/admin/ingestion/quarantine"),
+    ("POST", "/api/v1/admin/ingestion/github/pause"),
+    ("POST", "/api/v1/admin/ingestion/github/resume"),
+    ("GET", "/api/v1/debug/at"),
+    ("GET", "/api/v1/admin/degraded"),
+    ("POST", "/api/v1/admin/degraded/enter"),
+    ("POST", "/api/v1/admin/degraded/exit"),
+    ("GET", "/api/v1/admin/tiering"),
+    ("POST", "/api/v1/admin/tiering/run"),
+    ("GET", "/api/v1/admin/shadow"),
+    ("POST", "/api/v1/admin/shadow"),
+    ("GET", "/api/v1/admin/shadow/chains/chain-1"),
+    ("GET", "/api/v1/admin/profiling"),
+    ("POST", "/api/v1/admin/profiling"),
+    ("GET", "/api/v1/admin/billing/exports"),
+    ("POST", "/api/v1/admin/billing/exports/run"),
+    ("GET", "/api/v1/admin/summarization"),
+    ("POST", "/api/v1/admin/summarization/run"),
+    ("POST", "/api/v1/network/tls/reload"),
+    ("POST", "/api/v1/quality/benchmarks"),
+    ("DELETE", "/api/v1/quality/benchmarks/benchmark-1"),
+    ("POST", "/api/v1/quality/runs"),
+    ("POST", "/api/v1/dead-letters/signal-1/retry"),
+];
+
+#[tokio::test]
+async fn test_admin_routes_need_a_system_admin() {
+    let stack = dev::boot(&DevOptions::default()).await.unwrap();
+    let app = create_api_router(stack.server.clone());
+    let key = |username: &str| stack.users.iter().find(|user| user.username == username).unwrap().api_key.clone();
+    let admin = key("admin");
+
+    for (method, path) in ADMIN_ROUTES {
+        let reply = send(&app, request(method, path, &[], None)).await;
+        assert_eq!(reply.status, StatusCode::UNAUTHORIZED, "{} {} without a token", method, path);
+
+        for username in ["developer", "guest"] {
+            let reply = send(&app, request(method, path, &[("x-api-key", &key(username))], None)).await;
+            assert_eq!(reply.status, StatusCode::FORBIDDEN, "{} {} as {}", method, path, username);
+        }
+
+        // Reads get past the check for an admin; writes are left undone
+        if *method == "GET" {
+            let reply = send(&app, request(method, path, &[("x-api-key", &admin)], None)).await;
+            assert!(
+                !matches!(reply.status, StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN),
+                "{} {} as admin: {}",
+                method,
+                path,
+                reply.text()
+            );
+        }
+    }
+
+    stack.shutdown().await.unwrap();
+}
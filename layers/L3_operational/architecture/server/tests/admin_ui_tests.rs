@@ -0,0 +1,237 @@
+//! Embedded admin UI: assets and their cache headers, the token-gated
+//! session, and the API calls made by the page
+#![cfg(feature = "admin-ui")]
+
+mod common;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::{
+    http::{header, StatusCode},
+    Router,
+};
+use serde_json::{json, Value};
+use sqlx::SqlitePool;
+
+use hal9_core::auth::{
+    ApiKeyManager, CreateApiKeyRequest, CreateUserRequest, JwtManager, Permission, Permissions, UserManager, UserRole,
+};
+use hal9_core::{NeuronSignal, ServerConfig};
+use hal9_server::admin_ui::{self, IMMUTABLE, REVALIDATE};
+use hal9_server::api::create_api_router;
+use hal9_server::auth_middleware::AuthState;
+use hal9_server::chain_tracker::ChainStatus;
+use hal9_server::server::HAL9Server;
+
+use common::{call, request, send};
+
+/// A coder that takes one signal at a time and queues none
+fn config() -> ServerConfig {
+    common::mock_config(json!({
+        "server_id": "admin-ui-test",
+        "neurons": [{
+            "id": "coder", "layer": "L2", "forward_connections": [], "backward_connections": [],
+            "settings": {"concurrency": {"max_concurrent": 1, "max_queued": 0}},
+        }],
+        "claude": {
+            "mock_responses": {"L2": [{"trigger": "default", "response": "RESULT: Built", "delay_ms": 200}]},
+        },
+    }))
+}
+
+/// Versioned URL of `name` as linked from the page
+fn linked_url(page: &str, name: &str) -> String {
+    let start = page.find(&format!("/admin/assets/{}?v=", name)).expect("asset link");
+    let end = start + page[start..].find('"').unwrap();
+    page[start..end].to_string()
+}
+
+#[tokio::test]
+async fn test_page_and_assets_are_served_with_cache_headers() {
+    let server = Arc::new(HAL9Server::new(config()));
+    let app = create_api_router(server);
+
+    let reply = send(&app, request("GET", "/admin", &[], None)).await;
+    assert_eq!(reply.status, StatusCode::OK);
+    assert!(reply.header(header::CONTENT_TYPE).unwrap().starts_with("text/html"));
+    assert_eq!(reply.header(header::CACHE_CONTROL), Some(REVALIDATE));
+    let etag = reply.header(header::ETAG).unwrap().to_string();
+    assert!(!etag.is_empty());
+    let page = reply.text();
+    assert!(!page.contains("{{"), "unfilled asset link in the page");
+
+    // The page is revalidated by its ETag
+    let reply = send(&app, request("GET", "/admin", &[("if-none-match", &etag)], None)).await;
+    assert_eq!(reply.status, StatusCode::NOT_MODIFIED);
+    assert!(reply.body.is_empty());
+
+    for (name, content_type) in [("admin.js", "text/javascript"), ("admin.css", "text/css")] {
+        let url = linked_url(&page, name);
+        let reply = send(&app, request("GET", &url, &[], None)).await;
+        assert_eq!(reply.status, StatusCode::OK, "{}", url);
+        assert!(reply.header(header::CONTENT_TYPE).unwrap().starts_with(content_type));
+        assert_eq!(reply.header(header::CACHE_CONTROL), Some(IMMUTABLE));
+        assert_eq!(reply.body, admin_ui::embedded(name).unwrap().as_bytes());
+
+        // Only the URL naming the current content is cached for good
+        let stale = format!("/admin/assets/{}?v=0000", name);
+        let reply = send(&app, request("GET", &stale, &[], None)).await;
+        assert_eq!(reply.status, StatusCode::OK);
+        assert_eq!(reply.header(header::CACHE_CONTROL), Some(REVALIDATE));
+    }
+
+    let (status, _) = call(&app, "GET", "/admin/assets/secrets.txt", None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+/// Admin UI routes over a fresh auth database with an admin and a guest
+async fn gated_router() -> (Router, String, String, String) {
+    let pool = SqlitePool::connect("sqlite::memory:").await.unwrap();
+    let user_manager = Arc::new(UserManager::new(pool.clone()));
+    user_manager.initialize().await.unwrap();
+    let api_key_manager = Arc::new(ApiKeyManager::new(pool));
+    api_key_manager.initialize().await.unwrap();
+    let jwt_manager = Arc::new(JwtManager::new("test-secret-key".to_string()));
+
+    let mut tokens = Vec::new();
+    for (name, role) in [("root", UserRole::Admin), ("visitor", UserRole::Guest)] {
+        let user = user_manager.create_user(CreateUserRequest {
+            username: name.to_string(),
+            email: format!("{}@example.com", name),
+            password: "password123".to_string(),
+            role: Some(role),
+        }).await.unwrap();
+        tokens.push(jwt_manager.generate_access_token(&user.id, &user.username, &user.role).unwrap());
+    }
+    let root = user_manager.get_user_by_username("root").await.unwrap();
+    let key = api_key_manager.create_api_key(&root.id, CreateApiKeyRequest {
+        name: "keys-only".to_string(),
+        permissions: Permissions::with_permissions(vec![Permission::ManageApiKeys]),
+        ..Default::default()
+    }).await.unwrap().key;
+
+    let state = AuthState { jwt_manager, api_key_manager, user_manager };
+    let (admin, guest) = (tokens.remove(0), tokens.remove(0));
+    (admin_ui::router(Some(state)), admin, guest, key)
+}
+
+async fn session(app: &Router, headers: &[(&str, &str)]) -> (StatusCode, Value) {
+    let reply = send(app, request("GET", "/admin/session", headers, None)).await;
+    (reply.status, reply.json())
+}
+
+#[tokio::test]
+async fn test_session_needs_a_token_and_lists_what_it_may_do() {
+    let (app, admin, guest, keys_only) = gated_router().await;
+
+    // The page holds no data and loads without a token; its session does not
+    let (status, _) = call(&app, "GET", "/admin", None).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = session(&app, &[]).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, _) = session(&app, &[("authorization", "Bearer not-a-token")]).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let (status, body) = session(&app, &[("authorization", &format!("Bearer {}", admin))]).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["data"]["username"], "root");
+    assert_eq!(body["data"]["capabilities"].as_array().unwrap().len(), admin_ui::capabilities(None).len());
+
+    // A guest sees the neurons and signals but may not change them
+    let (status, body) = session(&app, &[("authorization", &format!("Bearer {}", guest))]).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["data"]["capabilities"], json!(["neurons", "signals", "dead_letters"]));
+
+    // A key the page has no use for is refused
+    let (status, _) = session(&app, &[("x-api-key", &keys_only)]).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+}
+
+/// Endpoints called by the embedded script, as `(method, path)`
+fn script_endpoints(script: &str) -> Vec<(String, String)> {
+    let table = script.split("const ENDPOINTS = {").nth(1).unwrap().split("};").next().unwrap();
+    table
+        .lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split('"').collect();
+            (fields.len() >= 5).then(|| (fields[1].to_string(), fields[3].to_string()))
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn test_script_calls_only_existing_endpoints() {
+    let server = Arc::new(HAL9Server::new(config()));
+    server.start().await.unwrap();
+    let app = create_api_router(server.clone());
+
+    let script = admin_ui::embedded("admin.js").unwrap();
+    let endpoints = script_endpoints(script);
+    assert!(endpoints.len() >= 8, "{:?}", endpoints);
+
+    // Every path in the script is one of the table's
+    for literal in script.split('"').skip(1).step_by(2).filter(|s| s.starts_with('/')) {
+        assert!(endpoints.iter().any(|(_, path)| path == literal), "{} is called outside ENDPOINTS", literal);
+    }
+
+    for (method, path) in &endpoints {
+        let uri = path.split('/')
+            .map(|segment| if segment.starts_with(':') { "missing" } else { segment })
+            .collect::<Vec<_>>()
+            .join("/");
+        let reply = send(&app, request(method, &uri, &[], None)).await;
+        assert_ne!(reply.status, StatusCode::METHOD_NOT_ALLOWED, "{} {}", method, path);
+        if reply.status == StatusCode::NOT_FOUND {
+            // A route that exists explains its 404; an unrouted path does not
+            assert!(!reply.body.is_empty(), "{} {} is not routed", method, path);
+        }
+    }
+
+    server.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_dead_letters_are_retried_and_spend_is_split_by_layer() {
+    let server = Arc::new(HAL9Server::new(config()));
+    server.start().await.unwrap();
+    let app = create_api_router(server.clone());
+
+    // The coder takes the first signal and turns the others away
+    for i in 0..3 {
+        let signal = NeuronSignal::forward("api-client", "coder", "API", "L2", format!("Build {}", i));
+        server.submit_signal(signal).await.unwrap();
+    }
+    tokio::time::sleep(Duration::from_millis(400)).await;
+    let letters = server.dead_letters();
+    assert!(!letters.is_empty());
+    let signal_id = letters[0].signal.signal_id.to_string();
+
+    let retry = format!("/api/v1/dead-letters/{}/retry", signal_id);
+    let (status, body) = call(&app, "POST", &retry, None).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    let chain_id = body["data"]["chain_id"].as_str().unwrap().to_string();
+    assert_eq!(server.dead_letters().len(), letters.len() - 1);
+
+    // The letter left the queue
+    let (status, _) = call(&app, "POST", &retry, None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let costs = tokio::time::timeout(Duration::from_secs(10), async {
+        loop {
+            let finished = server.get_chain_result(&chain_id).await.unwrap().status != ChainStatus::Running;
+            let costs = server.cost_stats().await;
+            if finished && !costs.by_layer.is_empty() {
+                return costs;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+    })
+    .await
+    .expect("retried chain did not finish");
+    assert_eq!(server.get_chain_result(&chain_id).await.unwrap().status, ChainStatus::Completed);
+    assert_eq!(costs.by_layer[0].layer, "L2");
+    assert!(costs.by_layer[0].signals >= 2);
+
+    server.shutdown().await.unwrap();
+}
@@ -0,0 +1,383 @@
+//! Approval gates: held outputs approved with edits, rejected branches,
+//! timeouts and gate nodes in chain results and visualizations
+
+mod common;
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use axum::http::StatusCode;
+use chrono::Utc;
+use serde_json::{json, Value};
+use tokio::sync::mpsc;
+
+use hal9_core::auth::{CreateApiKeyRequest, UserRole};
+use hal9_core::config::{ApprovalConfig, ApprovalTimeoutAction, SpeculationConfig};
+use hal9_core::{NeuronConfig, NeuronSignal};
+use hal9_server::api::create_api_router;
+use hal9_server::approvals::{self, ApprovalGates, TIMEOUT_ACTOR};
+use hal9_server::chain_tracker::{ChainStatus, ChainTracker, GateStatus, ORG_ID_KEY, PARENT_ID_KEY};
+use hal9_server::chain_visualization::{ChainGraph, MAX_NODES};
+use hal9_server::dev::{self, DevOptions};
+use hal9_server::error::ServerError;
+use hal9_server::neuron::{ManagedNeuron, NeuronRegistry};
+use hal9_server::router::{RoutingTable, SignalRouter};
+use hal9_server::speculation::Speculator;
+
+use common::{neuron_config, request, send, ScriptedProvider};
+
+/// Neurons answering with their response, each with the prompts it was sent
+type Network = HashMap<&'static str, Arc<Mutex<Vec<String>>>>;
+
+async fn start_router(
+    neurons: &[(NeuronConfig, &'static str)],
+    chain_tracker: &Arc<ChainTracker>,
+    gates: &Arc<ApprovalGates>,
+) -> (SignalRouter, Network) {
+    start_speculating_router(neurons, chain_tracker, gates, None).await
+}
+
+async fn start_speculating_router(
+    neurons: &[(NeuronConfig, &'static str)],
+    chain_tracker: &Arc<ChainTracker>,
+    gates: &Arc<ApprovalGates>,
+    speculation: Option<Speculator>,
+) -> (SignalRouter, Network) {
+    let registry = Arc::new(NeuronRegistry::new());
+    let mut prompts = HashMap::new();
+    for (config, response) in neurons {
+        let provider = ScriptedProvider::new(response);
+        prompts.insert(*response, provider.prompts.clone());
+        registry.register(ManagedNeuron::new(config.clone(), Box::new(provider)).unwrap()).await.unwrap();
+    }
+
+    let routing_table = Arc::new(RoutingTable::new());
+    let configs: Vec<NeuronConfig> = neurons.iter().map(|(config, _)| config.clone()).collect();
+    routing_table.build_from_configs(&configs);
+    let mut router = SignalRouter::new(registry, routing_table);
+    router.set_chain_tracker(chain_tracker.clone());
+    router.set_approvals(gates.clone());
+    if let Some(speculation) = speculation {
+        router.set_speculation(Arc::new(speculation));
+    }
+    router.start().await.unwrap();
+    (router, prompts)
+}
+
+fn config(timeout_action: ApprovalTimeoutAction) -> ApprovalConfig {
+    ApprovalConfig {
+        timeout_secs: 60,
+        timeout_action,
+        max_pending: 2,
+    }
+}
+
+const PLANNER: &str = "FORWARD_TO: browser\nCONTENT:\nSubmit the order form\nREQUIRES_APPROVAL: submits a payment form";
+const BROWSER: &str = "RESULT: Form submitted";
+
+#[tokio::test(start_paused = true)]
+async fn test_approved_edit_flows_downstream() {
+    let chain_tracker = Arc::new(ChainTracker::new());
+    let gates = Arc::new(ApprovalGates::new(config(ApprovalTimeoutAction::Reject), chain_tracker.clone()));
+    let neurons = [
+        (neuron_config("planner", "L4", &["browser"], Value::Null), PLANNER),
+        (neuron_config("browser", "L2", &[], Value::Null), BROWSER),
+    ];
+    let (router, prompts) = start_router(&neurons, &chain_tracker, &gates).await;
+
+    let mut root = NeuronSignal::forward("api", "planner", "API", "L4", "Order the parts".to_string());
+    let chain_id = chain_tracker.start(&mut root);
+    router.send_signal(root).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(200)).await;
+
+    // The planner's output waits, and nothing reached the browser
+    let pending = gates.pending();
+    assert_eq!(pending.len(), 1);
+    let approval = &pending[0];
+    assert_eq!(approval.chain_id.as_deref(), Some(chain_id.as_str()));
+    assert_eq!(approval.neuron_id, "planner");
+    assert_eq!(approval.reason, "submits a payment form");
+    assert_eq!(approval.content.as_deref(), Some("Submit the order form"));
+    assert_eq!(approval.forward_to, vec!["browser"]);
+    assert!(prompts[BROWSER].lock().unwrap().is_empty());
+
+    let result = chain_tracker.aggregate(&chain_id).unwrap();
+    assert_eq!(result.status, ChainStatus::Running);
+    assert_eq!(result.steps_completed, 0);
+    assert_eq!(result.gates.len(), 1);
+    assert_eq!(result.gates[0].status, GateStatus::Pending);
+    let record = chain_tracker.get(&chain_id).unwrap();
+    let mermaid = ChainGraph::from_record(&record, MAX_NODES).to_mermaid();
+    assert!(mermaid.contains("n0{{\"planner<br/>L4, 0ms, 0 tok, pending\"}}"), "{}", mermaid);
+
+    let edited = "Submit the order form without the newsletter signup";
+    let decision = gates.approve(&approval.id, Some(edited.to_string()), Some("alice")).await.unwrap();
+    assert_eq!(decision.status, GateStatus::Approved);
+    assert!(decision.edited);
+    assert_eq!(decision.released, 1);
+    tokio::time::sleep(Duration::from_millis(200)).await;
+
+    // The browser worked on the approver's content, not the planner's
+    let seen = prompts[BROWSER].lock().unwrap().clone();
+    assert_eq!(seen.len(), 1);
+    assert!(seen[0].contains(edited));
+    assert!(gates.pending().is_empty());
+
+    let record = chain_tracker.get(&chain_id).unwrap();
+    assert_eq!(record.status, ChainStatus::Completed);
+    assert_eq!(record.steps[0].output.as_deref(), Some(edited));
+    assert_eq!(record.steps[1].parent_id.as_deref(), Some(record.steps[0].signal_id.as_str()));
+    let gate = &record.gates[0];
+    assert_eq!(gate.status, GateStatus::Approved);
+    assert_eq!(gate.decided_by.as_deref(), Some("alice"));
+    assert!(gate.edited);
+    assert!(gate.decided_at.unwrap() >= gate.requested_at);
+
+    let dot = ChainGraph::from_record(&record, MAX_NODES).to_dot();
+    assert!(dot.contains("shape=hexagon"));
+    assert!(dot.contains("approved"));
+
+    // A decided approval cannot be decided again
+    assert!(matches!(gates.reject(&approval.id, None, None), Err(ServerError::NotFound(_))));
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_held_outputs_start_no_speculative_children() {
+    const STREAMED: &str = "FORWARD_TO: browser\nCONTENT:\nSubmit the order form\nREQUIRES_APPROVAL: submits a payment form\n";
+    let chain_tracker = Arc::new(ChainTracker::new());
+    let gates = Arc::new(ApprovalGates::new(config(ApprovalTimeoutAction::Reject), chain_tracker.clone()));
+    let neurons = [
+        (neuron_config("planner", "L4", &["browser"], Value::Null), STREAMED),
+        (neuron_config("browser", "L2", &[], Value::Null), BROWSER),
+    ];
+    let speculation = SpeculationConfig { layers: vec!["L4".to_string()], max_wasted_per_hour: 1.0 };
+    let (router, prompts) = start_speculating_router(&neurons, &chain_tracker, &gates, Speculator::new(&speculation)).await;
+
+    let mut root = NeuronSignal::forward("api", "planner", "API", "L4", "Order the parts".to_string());
+    let chain_id = chain_tracker.start(&mut root);
+    router.send_signal(root).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(200)).await;
+
+    // The streamed instruction was complete, but the browser never ran
+    let pending = gates.pending();
+    assert_eq!(pending.len(), 1);
+    assert_eq!(pending[0].forward_to, vec!["browser"]);
+    assert!(prompts[BROWSER].lock().unwrap().is_empty());
+
+    // Approved, the held forward runs once
+    gates.approve(&pending[0].id, None, Some("alice")).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert_eq!(prompts[BROWSER].lock().unwrap().len(), 1);
+    assert_eq!(chain_tracker.get(&chain_id).unwrap().status, ChainStatus::Completed);
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_rejection_fails_only_its_branch() {
+    let chain_tracker = Arc::new(ChainTracker::new());
+    let gates = Arc::new(ApprovalGates::new(config(ApprovalTimeoutAction::Reject), chain_tracker.clone()));
+    let neurons = [
+        (neuron_config("planner", "L4", &["buyer", "researcher"], Value::Null), "FORWARD_TO: buyer, researcher\nCONTENT: Find and buy the parts"),
+        (neuron_config("buyer", "L2", &[], json!({"approval": {"required": true}})), "RESULT: Parts bought"),
+        (neuron_config("researcher", "L2", &[], Value::Null), "RESULT: Parts compared"),
+    ];
+    let (router, _) = start_router(&neurons, &chain_tracker, &gates).await;
+
+    let mut root = NeuronSignal::forward("api", "planner", "API", "L4", "Order the parts".to_string());
+    let chain_id = chain_tracker.start(&mut root);
+    router.send_signal(root).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(500)).await;
+
+    // The buyer needs approval of everything it answers; the researcher not
+    let pending = gates.pending();
+    assert_eq!(pending.len(), 1);
+    assert_eq!(pending[0].neuron_id, "buyer");
+    assert_eq!(pending[0].reason, "Neuron buyer requires approval of its outputs");
+    assert!(pending[0].content.is_none());
+    let result = chain_tracker.aggregate(&chain_id).unwrap();
+    assert_eq!((result.status, result.steps_completed, result.pending_signals), (ChainStatus::Running, 2, 1));
+
+    gates.reject(&pending[0].id, Some("Over budget"), Some("bob")).unwrap();
+    let record = chain_tracker.get(&chain_id).unwrap();
+    assert_eq!(record.status, ChainStatus::Completed);
+    let step = |neuron: &str| record.steps.iter().find(|s| s.neuron_id == neuron).unwrap();
+    assert_eq!(step("researcher").output.as_deref(), Some("RESULT: Parts compared"));
+    assert!(step("planner").error.is_none());
+    assert_eq!(step("buyer").error.as_deref(), Some("Rejected at approval gate by bob: Over budget"));
+    assert_eq!(record.gates[0].status, GateStatus::Rejected);
+
+    let result = chain_tracker.aggregate(&chain_id).unwrap();
+    assert_eq!(result.errors, vec!["buyer: Rejected at approval gate by bob: Over budget"]);
+    let svg = ChainGraph::from_record(&record, MAX_NODES).to_svg();
+    assert!(svg.contains("rejected"));
+}
+
+fn held_signal(chain_tracker: &ChainTracker, content: &str) -> (String, NeuronSignal, Vec<NeuronSignal>) {
+    let mut root = NeuronSignal::forward("api", "planner", "API", "L4", content.to_string());
+    let chain_id = chain_tracker.start(&mut root);
+    let mut child = NeuronSignal::forward("planner", "browser", "L4", "L2", content.to_string());
+    child.metadata = root.metadata.clone();
+    child.metadata.insert(PARENT_ID_KEY.to_string(), root.signal_id.to_string());
+    (chain_id, root, vec![child])
+}
+
+#[tokio::test]
+async fn test_expired_approvals_are_rejected_by_default() {
+    let chain_tracker = Arc::new(ChainTracker::new());
+    let gates = ApprovalGates::new(config(ApprovalTimeoutAction::Reject), chain_tracker.clone());
+    let (chain_id, root, children) = held_signal(&chain_tracker, "Submit the form");
+    let approval = gates.hold(root, "FORWARD_TO: browser", children, "submits a form").unwrap();
+    assert_eq!(approval.timeout_action, ApprovalTimeoutAction::Reject);
+    assert_eq!((approval.expires_at - approval.requested_at).num_seconds(), 60);
+
+    // Nothing is due before the deadline
+    assert!(gates.resolve_expired(Utc::now()).await.is_empty());
+    let decisions = gates.resolve_expired(approval.expires_at).await;
+    assert_eq!(decisions.len(), 1);
+    assert_eq!(decisions[0].status, GateStatus::Rejected);
+    assert_eq!(decisions[0].decided_by, TIMEOUT_ACTOR);
+    assert!(gates.pending().is_empty());
+
+    let record = chain_tracker.get(&chain_id).unwrap();
+    assert_eq!(record.status, ChainStatus::Failed);
+    assert_eq!(record.steps[0].error.as_deref(), Some("Rejected at approval gate by timeout: timed out"));
+    assert_eq!(record.gates[0].decided_by.as_deref(), Some(TIMEOUT_ACTOR));
+}
+
+#[tokio::test]
+async fn test_expired_approvals_can_auto_approve() {
+    let chain_tracker = Arc::new(ChainTracker::new());
+    let gates = ApprovalGates::new(config(ApprovalTimeoutAction::Approve), chain_tracker.clone());
+    let (tx, mut rx) = mpsc::channel(10);
+    let (chain_id, root, children) = held_signal(&chain_tracker, "Submit the form");
+    let approval = gates.hold(root, "FORWARD_TO: browser", children, "submits a form").unwrap();
+
+    // Without a router the held signal has nowhere to go
+    let expired = approval.expires_at + chrono::Duration::seconds(1);
+    assert!(gates.resolve_expired(expired).await.is_empty());
+    assert_eq!(gates.pending().len(), 1);
+
+    gates.attach(tx);
+    let decisions = gates.resolve_expired(expired).await;
+    assert_eq!((decisions[0].status, decisions[0].edited, decisions[0].released), (GateStatus::Approved, false, 1));
+    let released = rx.recv().await.unwrap();
+    assert_eq!(released.to_neuron, "browser");
+    assert_eq!(released.payload.activation.content, "Submit the form");
+
+    let record = chain_tracker.get(&chain_id).unwrap();
+    assert_eq!(record.status, ChainStatus::Running);
+    assert_eq!(record.steps[0].output.as_deref(), Some("FORWARD_TO: browser"));
+    assert_eq!(record.gates[0].status, GateStatus::Approved);
+}
+
+#[tokio::test]
+async fn test_outputs_beyond_max_pending_fail_their_branch() {
+    let chain_tracker = Arc::new(ChainTracker::new());
+    let gates = ApprovalGates::new(config(ApprovalTimeoutAction::Reject), chain_tracker.clone());
+    for content in ["first", "second"] {
+        let (_, root, children) = held_signal(&chain_tracker, content);
+        assert!(gates.hold(root, content, children, "review").is_some());
+    }
+    let (chain_id, root, children) = held_signal(&chain_tracker, "third");
+    assert!(gates.hold(root, "third", children, "review").is_none());
+    assert_eq!(gates.pending().len(), 2);
+
+    let record = chain_tracker.get(&chain_id).unwrap();
+    assert_eq!(record.status, ChainStatus::Failed);
+    assert!(record.gates.is_empty());
+
+    let id = gates.pending()[0].id.clone();
+    assert!(matches!(gates.approve(&id, Some("  ".to_string()), None).await, Err(ServerError::InvalidInput(_))));
+    assert!(gates.get(&id).is_some());
+}
+
+#[tokio::test]
+async fn test_only_admins_of_the_chains_organization_decide() {
+    let stack = dev::boot(&DevOptions::default()).await.unwrap();
+    let app = create_api_router(stack.server.clone());
+    let mut root = NeuronSignal::forward("api", "planner", "API", "L4", "Submit the form".to_string());
+    root.metadata.insert(ORG_ID_KEY.to_string(), "acme".to_string());
+    stack.server.chain_tracker().start(&mut root);
+    let approval = stack.server.approvals().hold(root, "RESULT: ready", Vec::new(), "submits a form").unwrap();
+    assert_eq!(approval.org_id.as_deref(), Some("acme"));
+
+    let (user_manager, api_key_manager) = (stack.server.user_manager.clone().unwrap(), stack.server.api_key_manager.clone().unwrap());
+    let admin = user_manager.get_user_by_username("admin").await.unwrap();
+    let mut keys = HashMap::new();
+    for org in ["acme", "globex"] {
+        let key = api_key_manager
+            .create_api_key(&admin.id, CreateApiKeyRequest {
+                name: format!("{}-admin", org),
+                permissions: UserRole::Admin.default_permissions(),
+                org_id: Some(org.to_string()),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        keys.insert(org, key.key);
+    }
+    let developer = stack.users.iter().find(|user| user.username == "developer").unwrap().api_key.clone();
+
+    let approve = format!("/api/v1/approvals/{}/approve", approval.id);
+    let reject = format!("/api/v1/approvals/{}/reject", approval.id);
+    let routes = [("GET", "/api/v1/approvals"), ("POST", approve.as_str()), ("POST", reject.as_str())];
+    for (method, path) in routes {
+        let reply = send(&app, request(method, path, &[], None)).await;
+        assert_eq!(reply.status, StatusCode::UNAUTHORIZED, "{} {}", method, path);
+        let reply = send(&app, request(method, path, &[("x-api-key", &developer)], None)).await;
+        assert_eq!(reply.status, StatusCode::FORBIDDEN, "{} {}", method, path);
+    }
+
+    // Another organization's admin neither sees nor decides on it
+    let globex = [("x-api-key", keys["globex"].as_str())];
+    let reply = send(&app, request("GET", "/api/v1/approvals", &globex, None)).await;
+    assert_eq!(reply.status, StatusCode::OK);
+    assert_eq!(reply.json()["data"]["items"], json!([]));
+    for path in [&approve, &reject] {
+        let reply = send(&app, request("POST", path, &globex, None)).await;
+        assert_eq!(reply.status, StatusCode::FORBIDDEN, "{}", path);
+    }
+    assert!(stack.server.pending_approval(&approval.id).is_some());
+
+    let acme = [("x-api-key", keys["acme"].as_str())];
+    let reply = send(&app, request("GET", "/api/v1/approvals", &acme, None)).await;
+    assert_eq!(reply.json()["data"]["items"][0]["id"], json!(approval.id));
+    let reply = send(&app, request("POST", &reject, &acme, Some(json!({"reason": "wrong form"})))).await;
+    assert_eq!(reply.status, StatusCode::OK, "{}", reply.text());
+    assert_eq!(reply.json()["data"]["decided_by"], json!("admin"));
+
+    stack.shutdown().await.unwrap();
+}
+
+#[test]
+fn test_approval_directive_and_settings() {
+    assert_eq!(approvals::requested("CONTENT: x\nREQUIRES_APPROVAL: submits a form").as_deref(), Some("submits a form"));
+    assert_eq!(approvals::requested("REQUIRES_APPROVAL:").as_deref(), Some("Approval requested by the neuron"));
+    assert!(approvals::requested("RESULT: done").is_none());
+
+    // The directive closes the content forwarded before it
+    let neuron = ManagedNeuron::new(
+        neuron_config("planner", "L4", &["browser"], Value::Null),
+        Box::new(ScriptedProvider::new("")),
+    )
+    .unwrap();
+    let root = NeuronSignal::forward("api", "planner", "API", "L4", "Order".to_string());
+    let forwarded = neuron.parse_response(PLANNER, &root);
+    assert_eq!(forwarded[0].payload.activation.content, "Submit the order form");
+    assert!(!neuron.requires_approval());
+    assert_eq!(neuron.approval_reason(PLANNER).as_deref(), Some("submits a payment form"));
+    assert!(neuron.approval_reason(BROWSER).is_none());
+
+    let mut invalid = neuron_config("buyer", "L2", &[], Value::Null);
+    invalid.settings.insert("approval".to_string(), json!({"required": "yes"}));
+    assert!(ManagedNeuron::new(invalid, Box::new(ScriptedProvider::new(""))).is_err());
+
+    assert!(approvals::validate(&ApprovalConfig::default()).is_ok());
+    for invalid in [
+        ApprovalConfig { timeout_secs: 0, ..Default::default() },
+        ApprovalConfig { timeout_secs: 31 * 24 * 3600, ..Default::default() },
+        ApprovalConfig { max_pending: 0, ..Default::default() },
+    ] {
+        assert!(approvals::validate(&invalid).is_err());
+    }
+}
@@ -1,9 +1,73 @@
 //! Authentication integration tests
 
-use hal9_core::auth::{JwtManager, UserManager, CreateUserRequest, ApiKeyManager, CreateApiKeyRequest, Permission, UserRole, Permissions};
+mod common;
+
+use axum::{
+    http::StatusCode,
+    middleware,
+    response::IntoResponse,
+    routing::get,
+    Extension, Json, Router,
+};
+use chrono::Duration;
+use hal9_core::auth::{
+    JwtClaims, JwtManager, User, UserManager, CreateUserRequest, ApiKeyManager, ApiKeyLimits,
+    CreateApiKeyRequest, Permission, UserRole, Permissions, AuthError,
+};
+use hal9_server::api_auth::{self, AuthApiState};
+use hal9_server::auth_middleware::{auth_middleware, require_permission, AuthState, AuthUser};
 use sqlx::SqlitePool;
+use std::sync::Arc;
 use anyhow::Result;
 
+/// Auth state over a fresh in-memory database with one user of `role`
+async fn auth_state(role: UserRole) -> Result<(AuthState, User)> {
+    let pool = SqlitePool::connect("sqlite::memory:").await?;
+    let user_manager = Arc::new(UserManager::new(pool.clone()));
+    user_manager.initialize().await?;
+    let api_key_manager = Arc::new(ApiKeyManager::new(pool));
+    api_key_manager.initialize().await?;
+    
+    let user = user_manager.create_user(CreateUserRequest {
+        username: "keyowner".to_string(),
+        email: "keys@example.com".to_string(),
+        password: "password123".to_string(),
+        role: Some(role),
+    }).await?;
+    
+    let state = AuthState {
+        jwt_manager: Arc::new(JwtManager::new("test-secret-key".to_string())),
+        api_key_manager,
+        user_manager,
+    };
+    Ok((state, user))
+}
+
+fn key_request(name: &str, permissions: Vec<Permission>) -> CreateApiKeyRequest {
+    CreateApiKeyRequest {
+        name: name.to_string(),
+        permissions: Permissions::with_permissions(permissions),
+        ..Default::default()
+    }
+}
+
+/// Routes guarded by a single permission each, echoing the caller's claims
+fn guarded_router(state: AuthState) -> Router {
+    async fn whoami(Extension(user): Extension<AuthUser>, Extension(claims): Extension<JwtClaims>) -> String {
+        format!("{} {} {} {}", user.user_id, claims.sub, claims.username, claims.role)
+    }
+    
+    Router::new()
+        .route("/view", get(whoami).route_layer(middleware::from_fn(require_permission(Permission::ViewNeuron))))
+        .route("/send", get(whoami).route_layer(middleware::from_fn(require_permission(Permission::SendSignal))))
+        .layer(middleware::from_fn_with_state(state, auth_middleware))
+}
+
+async fn call(app: &Router, path: &str, header: (&str, &str)) -> (StatusCode, String) {
+    let reply = common::send(app, common::request("GET", path, &[header], None)).await;
+    (reply.status, reply.text())
+}
+
 #[tokio::test]
 async fn test_jwt_authentication_flow() -> Result<()> {
     // Create temporary database
@@ -90,6 +154,7 @@ async fn test_api_key_authentication() -> Result<()> {
         name: "test-key".to_string(),
         permissions: Permissions::with_permissions(vec![Permission::ViewNeuron, Permission::SendSignal]),
         expires_in_days: None,
+        ..Default::default()
     };
     
     let api_key_response = api_key_manager
@@ -147,4 +212,179 @@ async fn test_user_roles_and_permissions() -> Result<()> {
     
     println!("✅ Roles and permissions test passed!");
     Ok(())
-}
\ No newline at end of file
+}
+
+#[tokio::test]
+async fn test_api_key_scopes_are_enforced() -> Result<()> {
+    let (state, user) = auth_state(UserRole::User).await?;
+    let key = state.api_key_manager
+        .create_api_key(&user.id, key_request("read-only", vec![Permission::ViewNeuron]))
+        .await?
+        .key;
+    let token = state.jwt_manager.generate_access_token(&user.id, &user.username, &user.role)?;
+    let app = guarded_router(state);
+    
+    // The key only reaches what its scopes allow, though its owner may send signals
+    let (status, key_body) = call(&app, "/view", ("X-API-Key", &key)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(call(&app, "/send", ("X-API-Key", &key)).await.0, StatusCode::FORBIDDEN);
+    assert_eq!(call(&app, "/send", ("Authorization", &format!("Bearer {}", token))).await.0, StatusCode::OK);
+    
+    // Handlers see the same identity whichever credential was used
+    let (_, token_body) = call(&app, "/view", ("Authorization", &format!("Bearer {}", token))).await;
+    assert_eq!(key_body, token_body);
+    assert_eq!(key_body, format!("{} {} keyowner user", user.id, user.id));
+    
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_api_key_scopes_narrow_with_owner_role() -> Result<()> {
+    let (state, user) = auth_state(UserRole::Guest).await?;
+    
+    // A guest cannot send signals, so a key claiming to is cut back to its owner's rights
+    let key = state.api_key_manager
+        .create_api_key(&user.id, key_request("escalating", vec![Permission::ViewNeuron, Permission::SendSignal]))
+        .await?
+        .key;
+    let app = guarded_router(state);
+    
+    assert_eq!(call(&app, "/view", ("X-API-Key", &key)).await.0, StatusCode::OK);
+    assert_eq!(call(&app, "/send", ("X-API-Key", &key)).await.0, StatusCode::FORBIDDEN);
+    
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_api_key_revocation_applies_to_next_request() -> Result<()> {
+    let (state, user) = auth_state(UserRole::User).await?;
+    let created = state.api_key_manager
+        .create_api_key(&user.id, key_request("revoked", vec![Permission::ViewNeuron]))
+        .await?;
+    let app = guarded_router(state.clone());
+    
+    assert_eq!(call(&app, "/view", ("X-API-Key", &created.key)).await.0, StatusCode::OK);
+    
+    // Same router, same manager: nothing cached survives the revocation
+    state.api_key_manager.revoke_api_key(&user.id, &created.id).await?;
+    assert_eq!(call(&app, "/view", ("X-API-Key", &created.key)).await.0, StatusCode::UNAUTHORIZED);
+    assert!(matches!(
+        state.api_key_manager.validate_api_key(&created.key).await,
+        Err(AuthError::ApiKeyRevoked)
+    ));
+    
+    // Usage was recorded for the one request that got through
+    let info = state.api_key_manager.get_api_key(&user.id, &created.id).await?;
+    assert_eq!(info.request_count, 1);
+    assert!(info.last_used_at.is_some());
+    
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_api_key_rotation_overlap_window() -> Result<()> {
+    let (state, user) = auth_state(UserRole::User).await?;
+    let manager = &state.api_key_manager;
+    let original = manager
+        .create_api_key(&user.id, key_request("rotating", vec![Permission::ViewNeuron]))
+        .await?;
+    
+    // Within the overlap both secrets work and resolve to the same key
+    let rotated = manager.rotate_api_key(&user.id, &original.id, Duration::minutes(60)).await?;
+    assert_ne!(rotated.key, original.key);
+    assert_eq!(rotated.id, original.id);
+    assert!(rotated.previous_expires_at.is_some());
+    assert_eq!(manager.validate_api_key(&original.key).await?.0.id, original.id);
+    assert_eq!(manager.validate_api_key(&rotated.key).await?.0.id, original.id);
+    
+    // Listing shows the new prefix and never a secret
+    let keys = manager.list_user_api_keys(&user.id).await?;
+    assert_eq!(keys.len(), 1);
+    assert_eq!(keys[0].prefix, rotated.prefix);
+    assert!(rotated.key.starts_with(&rotated.prefix));
+    assert!(!serde_json::to_string(&keys[0])?.contains(&rotated.key));
+    
+    // Rotating again without overlap retires the secret at once, and the
+    // original, two rotations back, is gone for good
+    let latest = manager.rotate_api_key(&user.id, &original.id, Duration::zero()).await?;
+    assert!(matches!(manager.validate_api_key(&rotated.key).await, Err(AuthError::ApiKeyNotFound)));
+    assert!(matches!(manager.validate_api_key(&original.key).await, Err(AuthError::ApiKeyNotFound)));
+    assert!(manager.validate_api_key(&latest.key).await.is_ok());
+    
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_api_key_rate_limit_and_budget() -> Result<()> {
+    let (state, user) = auth_state(UserRole::User).await?;
+    let manager = &state.api_key_manager;
+    let created = manager
+        .create_api_key(&user.id, CreateApiKeyRequest {
+            limits: ApiKeyLimits {
+                rate_limit_per_minute: Some(2),
+                token_budget: Some(1000),
+            },
+            ..key_request("limited", vec![Permission::ViewNeuron])
+        })
+        .await?;
+    
+    assert!(manager.validate_api_key(&created.key).await.is_ok());
+    manager.record_token_usage(&created.id, 1000).await?;
+    assert!(matches!(manager.validate_api_key(&created.key).await, Err(AuthError::BudgetExhausted)));
+    
+    let unbudgeted = manager
+        .create_api_key(&user.id, CreateApiKeyRequest {
+            limits: ApiKeyLimits { rate_limit_per_minute: Some(2), token_budget: None },
+            ..key_request("throttled", vec![Permission::ViewNeuron])
+        })
+        .await?;
+    let app = guarded_router(state.clone());
+    let mut statuses = Vec::new();
+    for _ in 0..5 {
+        statuses.push(call(&app, "/view", ("X-API-Key", &unbudgeted.key)).await.0);
+    }
+    // Five requests span at most two minute windows of two requests each
+    assert_eq!(statuses[..2], [StatusCode::OK, StatusCode::OK]);
+    assert!(statuses.contains(&StatusCode::TOO_MANY_REQUESTS), "{:?}", statuses);
+    
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_api_keys_are_bound_only_to_the_callers_organization() -> Result<()> {
+    let (state, user) = auth_state(UserRole::User).await?;
+    let state = Arc::new(AuthApiState {
+        user_manager: state.user_manager,
+        jwt_manager: state.jwt_manager,
+        api_key_manager: state.api_key_manager,
+    });
+    let caller = |org_id: Option<&str>, role: UserRole| AuthUser {
+        user_id: user.id.clone(),
+        username: user.username.clone(),
+        role: role.to_string(),
+        permissions: role.default_permissions(),
+        org_id: org_id.map(str::to_string),
+        api_key_id: None,
+    };
+    let create = |caller: AuthUser, org_id: Option<&str>| {
+        let request = CreateApiKeyRequest {
+            org_id: org_id.map(str::to_string),
+            ..key_request("org", vec![Permission::ViewNeuron])
+        };
+        api_auth::create_api_key(Extension(caller), axum::extract::State(state.clone()), Json(request))
+    };
+
+    // A member of acme cannot mint a key for globex, and its own keys default to acme
+    let err = create(caller(Some("acme"), UserRole::User), Some("globex")).await.unwrap_err();
+    assert_eq!(err.into_response().status(), StatusCode::FORBIDDEN);
+    let (_, Json(key)) = create(caller(Some("acme"), UserRole::User), None).await.unwrap();
+    assert_eq!(key.org_id.as_deref(), Some("acme"));
+
+    // Without an organization, naming one needs a system admin
+    let err = create(caller(None, UserRole::User), Some("globex")).await.unwrap_err();
+    assert_eq!(err.into_response().status(), StatusCode::FORBIDDEN);
+    let (_, Json(key)) = create(caller(None, UserRole::Admin), Some("globex")).await.unwrap();
+    assert_eq!(key.org_id.as_deref(), Some("globex"));
+    
+    Ok(())
+}
@@ -0,0 +1,251 @@
+//! Tests for autoscaling recommendations, pending signal tracking and drain
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::oneshot;
+use uuid::Uuid;
+
+use hal9_core::config::AutoscalingConfig;
+use hal9_core::NeuronSignal;
+use hal9_server::chain_tracker::ChainTracker;
+use hal9_server::fair_scheduler::FairScheduler;
+use hal9_server::scaling::autoscale::recommend;
+use hal9_server::scaling::{Autoscaler, DrainOutcome, LoadSignals, PendingSignals, ScalingDriver};
+
+fn config() -> AutoscalingConfig {
+    AutoscalingConfig {
+        target_queue_age_secs: 10.0,
+        target_pending_signals: 50.0,
+        target_saturation: 0.8,
+        min_replicas: 1,
+        max_replicas: 10,
+        tolerance: 0.1,
+        drain_timeout_secs: 30,
+    }
+}
+
+fn load(pending: usize, oldest_secs: f64, saturation: f64) -> LoadSignals {
+    LoadSignals {
+        pending_signals: pending,
+        oldest_pending_age_secs: oldest_secs,
+        pool_saturation: saturation,
+        ..Default::default()
+    }
+}
+
+fn autoscaler(capacity: usize) -> (Arc<Autoscaler>, Arc<FairScheduler>, Arc<ChainTracker>) {
+    let scheduler = Arc::new(FairScheduler::new(capacity));
+    let tracker = Arc::new(ChainTracker::new());
+    let autoscaler = Arc::new(Autoscaler::new(
+        config(),
+        Arc::new(PendingSignals::new()),
+        scheduler.clone(),
+        tracker.clone(),
+    ));
+    (autoscaler, scheduler, tracker)
+}
+
+fn start_chain(tracker: &ChainTracker) -> String {
+    let mut signal = NeuronSignal::forward("client", "planner", "input", "L4", "task".to_string());
+    tracker.start(&mut signal)
+}
+
+#[test]
+fn test_idle_scales_down_to_min() {
+    let recommendation = recommend(&config(), 4, &load(0, 0.0, 0.0));
+
+    assert_eq!(recommendation.recommended_replicas, 1);
+    assert_eq!(recommendation.driver, None);
+    assert_eq!(recommendation.ratio, 0.0);
+}
+
+#[test]
+fn test_queue_age_over_target_scales_up() {
+    // Oldest signal waited twice the target
+    let recommendation = recommend(&config(), 3, &load(10, 20.0, 0.5));
+
+    assert_eq!(recommendation.driver, Some(ScalingDriver::QueueAge));
+    assert_eq!(recommendation.ratio, 2.0);
+    assert_eq!(recommendation.recommended_replicas, 6);
+}
+
+#[test]
+fn test_highest_ratio_wins() {
+    // Queue age is under target but saturation is over it
+    let recommendation = recommend(&config(), 2, &load(10, 5.0, 1.0));
+
+    assert_eq!(recommendation.driver, Some(ScalingDriver::PoolSaturation));
+    assert_eq!(recommendation.recommended_replicas, 3);
+
+    let recommendation = recommend(&config(), 2, &load(150, 5.0, 0.4));
+    assert_eq!(recommendation.driver, Some(ScalingDriver::PendingSignals));
+    assert_eq!(recommendation.recommended_replicas, 6);
+}
+
+#[test]
+fn test_within_tolerance_holds() {
+    for saturation in [0.75, 0.8, 0.85] {
+        let recommendation = recommend(&config(), 5, &load(0, 0.0, saturation));
+        assert_eq!(recommendation.recommended_replicas, 5, "saturation {}", saturation);
+    }
+
+    // Just past the tolerance moves
+    let recommendation = recommend(&config(), 5, &load(0, 0.0, 0.9));
+    assert_eq!(recommendation.recommended_replicas, 6);
+}
+
+#[test]
+fn test_partial_load_scales_down() {
+    // Half the target spread over 8 replicas fits in 4
+    let recommendation = recommend(&config(), 8, &load(25, 2.0, 0.3));
+
+    assert_eq!(recommendation.driver, Some(ScalingDriver::PendingSignals));
+    assert_eq!(recommendation.recommended_replicas, 4);
+}
+
+#[test]
+fn test_clamped_to_bounds() {
+    let recommendation = recommend(&config(), 8, &load(1000, 300.0, 1.0));
+    assert_eq!(recommendation.recommended_replicas, 10);
+
+    let config = AutoscalingConfig { min_replicas: 3, ..config() };
+    let recommendation = recommend(&config, 8, &load(0, 0.0, 0.0));
+    assert_eq!(recommendation.recommended_replicas, 3);
+
+    // A max below the min keeps the min
+    let config = AutoscalingConfig { min_replicas: 3, max_replicas: 2, ..config };
+    let recommendation = recommend(&config, 1, &load(1000, 300.0, 1.0));
+    assert_eq!(recommendation.recommended_replicas, 3);
+}
+
+#[test]
+fn test_zero_replicas_counts_as_one() {
+    let recommendation = recommend(&config(), 0, &load(100, 0.0, 0.0));
+
+    assert_eq!(recommendation.current_replicas, 1);
+    assert_eq!(recommendation.recommended_replicas, 2);
+}
+
+#[test]
+fn test_disabled_target_is_ignored() {
+    let config = AutoscalingConfig { target_queue_age_secs: 0.0, ..config() };
+    let recommendation = recommend(&config, 2, &load(0, 500.0, 0.0));
+
+    assert_eq!(recommendation.driver, None);
+    assert_eq!(recommendation.recommended_replicas, 1);
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_pending_signals_age_until_processed() {
+    let pending = Arc::new(PendingSignals::new());
+    let first = Uuid::new_v4();
+    let second = Uuid::new_v4();
+
+    pending.enter(first);
+    tokio::time::advance(Duration::from_secs(5)).await;
+    pending.enter(second);
+    tokio::time::advance(Duration::from_secs(2)).await;
+
+    assert_eq!(pending.len(), 2);
+    assert_eq!(pending.oldest_age(), Duration::from_secs(7));
+
+    // A guard keeps the original entry time and removes the signal on drop
+    let guard = pending.guard(first);
+    assert_eq!(pending.oldest_age(), Duration::from_secs(7));
+    drop(guard);
+    assert_eq!(pending.len(), 1);
+    assert_eq!(pending.oldest_age(), Duration::from_secs(2));
+
+    drop(pending.guard(second));
+    assert!(pending.is_empty());
+    assert_eq!(pending.oldest_age(), Duration::ZERO);
+}
+
+#[tokio::test]
+async fn test_load_reports_saturation() {
+    let (autoscaler, scheduler, _tracker) = autoscaler(4);
+
+    let _first = scheduler.acquire("alice").await;
+    let _second = scheduler.acquire("bob").await;
+    autoscaler.pending().enter(Uuid::new_v4());
+
+    let load = autoscaler.load();
+    assert_eq!(load.slots_in_use, 2);
+    assert_eq!(load.slots_capacity, 4);
+    assert_eq!(load.pool_saturation, 0.5);
+    assert_eq!(load.pending_signals, 1);
+    assert!(!load.draining);
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_drain_waits_for_running_chains() {
+    let (autoscaler, _scheduler, tracker) = autoscaler(4);
+    let chain_id = start_chain(&tracker);
+
+    let (terminate_tx, terminate_rx) = oneshot::channel::<()>();
+    let drained = autoscaler.drain_on(async move {
+        let _ = terminate_rx.await;
+    });
+
+    assert!(!autoscaler.is_draining());
+    terminate_tx.send(()).unwrap();
+    tokio::time::sleep(Duration::from_secs(1)).await;
+
+    let status = autoscaler.drain_status();
+    assert!(status.draining);
+    assert_eq!(status.running_chains, 1);
+    assert!(!status.safe_to_terminate);
+    assert!(!drained.is_finished());
+
+    tracker.cancel(&chain_id);
+    assert_eq!(drained.await.unwrap(), DrainOutcome::Drained);
+    assert!(autoscaler.drain_status().safe_to_terminate);
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_drain_times_out() {
+    let (autoscaler, _scheduler, tracker) = autoscaler(4);
+    start_chain(&tracker);
+
+    let started = tokio::time::Instant::now();
+    assert_eq!(autoscaler.drain().await, DrainOutcome::TimedOut);
+    assert_eq!(started.elapsed(), Duration::from_secs(30));
+}
+
+#[tokio::test]
+async fn test_begin_drain_is_idempotent() {
+    let (autoscaler, _scheduler, _tracker) = autoscaler(4);
+
+    assert!(autoscaler.begin_drain());
+    let since = autoscaler.drain_status().since;
+    assert!(!autoscaler.begin_drain());
+    assert_eq!(autoscaler.drain_status().since, since);
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_sigterm_drains() {
+    let (autoscaler, _scheduler, tracker) = autoscaler(4);
+    let chain_id = start_chain(&tracker);
+    let drained = autoscaler.drain_on_sigterm().unwrap();
+
+    let status = std::process::Command::new("kill")
+        .args(["-TERM", &std::process::id().to_string()])
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while !autoscaler.is_draining() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("SIGTERM did not start a drain");
+    assert!(!autoscaler.drain_status().safe_to_terminate);
+
+    tracker.cancel(&chain_id);
+    let outcome = tokio::time::timeout(Duration::from_secs(5), drained).await.unwrap().unwrap();
+    assert_eq!(outcome, DrainOutcome::Drained);
+}
@@ -0,0 +1,90 @@
+//! Backward signals: gradient feedback in chain results and listing recent
+//! gradient activity
+
+mod common;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::http::StatusCode;
+use serde_json::{json, Value};
+
+use hal9_core::{Gradient, NeuronSignal};
+use hal9_server::api::create_api_router;
+use hal9_server::chain_tracker::{mark_gradient, ChainStatus};
+use hal9_server::server::HAL9Server;
+
+use common::call;
+
+async fn finish(server: &HAL9Server, chain_id: &str) {
+    tokio::time::timeout(Duration::from_secs(10), async {
+        while server.get_chain_result(chain_id).await.unwrap().status == ChainStatus::Running {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("chain did not finish");
+}
+
+#[tokio::test]
+async fn test_list_filters_backward_signals() {
+    let config = common::mock_config(json!({
+        "server_id": "backward-test",
+        "neurons": [
+            {"id": "planner", "layer": "L4", "forward_connections": [], "backward_connections": []},
+            {"id": "coder", "layer": "L2", "forward_connections": [], "backward_connections": ["planner"]},
+        ],
+        "claude": {
+            "mock_responses": {"L4": [{"trigger": "default", "response": "RESULT: Planned", "delay_ms": 0}]},
+        },
+        "memory": {"enabled": false},
+    }));
+    let server = Arc::new(HAL9Server::new(config));
+    server.start().await.unwrap();
+    let started = chrono::Utc::now() - chrono::Duration::seconds(1);
+
+    let forward = NeuronSignal::forward("api-client", "planner", "API", "L4", "Plan it".into());
+    let forward_chain = server.submit_signal(forward).await.unwrap();
+    let mut feedback = NeuronSignal::backward("coder", "planner", "L2", "L4", Gradient::new("timeout".to_string(), 0.5));
+    mark_gradient(&mut feedback, "timeout", 0.5);
+    let feedback_chain = server.submit_signal(feedback).await.unwrap();
+    finish(&server, &forward_chain).await;
+    finish(&server, &feedback_chain).await;
+    let app = create_api_router(server.clone());
+
+    let (status, body) = call(&app, "GET", "/api/v1/signals?propagation=backward", None).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    let items = body["data"]["items"].as_array().unwrap();
+    assert_eq!(items.len(), 1, "{}", body);
+    assert_eq!(items[0]["direction"], "Backward");
+    assert_eq!(items[0]["chain_id"], feedback_chain.as_str());
+    assert_eq!(items[0]["gradient"]["from_neuron"], "coder");
+    assert_eq!(items[0]["gradient"]["error_class"], "timeout");
+    assert_eq!(items[0]["gradient"]["attenuation"], 0.5);
+
+    let since = urlencoding(&started.to_rfc3339());
+    let (_, body) = call(&app, "GET", &format!("/api/v1/signals?since={}&neuron_id=planner", since), None).await;
+    assert_eq!(body["data"]["items"].as_array().unwrap().len(), 2, "{}", body);
+    let later = urlencoding(&(chrono::Utc::now() + chrono::Duration::minutes(1)).to_rfc3339());
+    let (_, body) = call(&app, "GET", &format!("/api/v1/signals?since={}", later), None).await;
+    assert_eq!(body["data"]["items"], json!([]));
+
+    let (status, _) = call(&app, "GET", "/api/v1/signals?propagation=sideways", None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    // The feedback is listed in its chain's result, which has no output
+    let (_, body) = call(&app, "GET", &format!("/api/v1/chains/{}", feedback_chain), None).await;
+    assert_eq!(body["data"]["backward_steps"], 1);
+    assert_eq!(body["data"]["feedback"][0]["to_neuron"], "planner");
+    assert_eq!(body["data"]["final_output"], Value::Null);
+    let (_, body) = call(&app, "GET", &format!("/api/v1/chains/{}", forward_chain), None).await;
+    assert_eq!(body["data"]["backward_steps"], 0);
+    assert!(body["data"].get("feedback").is_none());
+
+    server.shutdown().await.unwrap();
+}
+
+/// Escape the `+` of an RFC 3339 offset
+fn urlencoding(time: &str) -> String {
+    time.replace('+', "%2B")
+}
@@ -0,0 +1,317 @@
+//! Billing export: files are deterministic, late cost records produce
+//! amendments, and delivery to an S3-compatible endpoint is signed
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use axum::{
+    body::Bytes,
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    routing::put,
+    Router,
+};
+use chrono::{DateTime, Duration, NaiveDate, TimeZone, Utc};
+use parking_lot::Mutex;
+use sha2::{Digest, Sha256};
+use sqlx::SqlitePool;
+
+use hal9_core::{
+    config::{AuthConfig, BillingDestination, BillingExportConfig},
+    memory::{FsObjectStore, ObjectStore},
+    secrets::SecretString,
+    NeuronSignal, Result,
+};
+use hal9_server::{
+    billing_export::{self, BillingExporter, BillingFile, Destination, StorageMeter, CSV_HEADER},
+    chain_tracker::{ChainRecord, ChainTracker, ORG_ID_KEY},
+    memory_tiering::S3ObjectStore,
+    org_usage::OrgUsageStore,
+};
+
+const HAIKU: &str = "claude-3-haiku-20240307";
+const SONNET: &str = "claude-3-sonnet-20240229";
+
+fn day() -> NaiveDate {
+    NaiveDate::from_ymd_opt(2026, 10, 14).unwrap()
+}
+
+fn at(hour: u32) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2026, 10, 14, hour, 0, 0).unwrap()
+}
+
+/// Shortly after the day ended
+fn next_morning() -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2026, 10, 15, 0, 30, 0).unwrap()
+}
+
+/// A finished one-step chain of `org`
+fn chain(org: &str, created_at: DateTime<Utc>, model: &str, prompt: u32, completion: u32) -> ChainRecord {
+    let tracker = ChainTracker::new();
+    let mut root = NeuronSignal::forward("api", "planner", "API", "L2", "work".into());
+    root.metadata.insert(ORG_ID_KEY.to_string(), org.to_string());
+    let chain_id = tracker.start(&mut root);
+    tracker.record_usage(&root, prompt, completion);
+    tracker.record_model(&root, model);
+    tracker.record_step(&root, Ok("done"), 0);
+
+    let mut record = tracker.get(&chain_id).unwrap();
+    record.created_at = created_at;
+    record.completed_at = record.completed_at.map(|_| created_at + Duration::seconds(2));
+    record
+}
+
+/// Bytes stored per organization, as set by the test
+#[derive(Default)]
+struct FixedMeter(Mutex<HashMap<String, u64>>);
+
+impl FixedMeter {
+    fn set(&self, org_id: &str, bytes: u64) {
+        self.0.lock().insert(org_id.to_string(), bytes);
+    }
+}
+
+#[async_trait]
+impl StorageMeter for FixedMeter {
+    async fn bytes_by_org(&self) -> Result<HashMap<String, u64>> {
+        Ok(self.0.lock().clone())
+    }
+}
+
+struct Fixture {
+    usage: OrgUsageStore,
+    exporter: BillingExporter,
+    artifacts: Arc<FixedMeter>,
+}
+
+fn config() -> BillingExportConfig {
+    BillingExportConfig { enabled: true, lookback_days: 1, ..Default::default() }
+}
+
+/// Chains of acme and globex on the day, memory of initech, and an
+/// exporter delivering to `destination`
+async fn fixture(destination: Destination) -> Fixture {
+    let pool = SqlitePool::connect("sqlite::memory:").await.unwrap();
+    let usage = OrgUsageStore::new(pool.clone(), SONNET);
+    usage.initialize().await.unwrap();
+    for record in [
+        chain("acme", at(9), HAIKU, 1000, 400),
+        chain("acme", at(11), SONNET, 2000, 100),
+        chain("acme", at(23), HAIKU, 300, 300),
+        chain("globex", at(12), SONNET, 9000, 900),
+        // The day before, outside the file
+        chain("acme", at(9) - Duration::days(1), HAIKU, 5000, 5000),
+    ] {
+        usage.record_chain(&record).await.unwrap();
+    }
+
+    let artifacts = Arc::new(FixedMeter::default());
+    artifacts.set("acme", 20_480);
+    let memory = Arc::new(FixedMeter::default());
+    memory.set("acme", 1_048_576);
+    memory.set("initech", 512);
+
+    let exporter = BillingExporter::new(config(), pool, destination)
+        .with_artifacts(artifacts.clone())
+        .with_memory(memory);
+    exporter.initialize().await.unwrap();
+    Fixture { usage, exporter, artifacts }
+}
+
+fn by_org(files: Vec<BillingFile>) -> BTreeMap<String, BillingFile> {
+    files.into_iter().map(|file| (file.usage.org_id.clone(), file)).collect()
+}
+
+#[tokio::test]
+async fn test_export_is_deterministic() {
+    let dir = tempfile::tempdir().unwrap();
+    let objects = Arc::new(FsObjectStore::new(dir.path()));
+    let Fixture { exporter, .. } = fixture(Destination::Store(objects.clone())).await;
+
+    let first = by_org(exporter.files(day(), next_morning()).await.unwrap());
+    let again = by_org(exporter.files(day(), next_morning() + Duration::hours(5)).await.unwrap());
+    assert_eq!(first.keys().collect::<Vec<_>>(), vec!["acme", "globex", "initech"]);
+    for (org, file) in &first {
+        assert_eq!(file.to_json(), again[org].to_json(), "{}", org);
+        assert_eq!(file.to_csv(), again[org].to_csv(), "{}", org);
+        assert_eq!((file.revision, file.correction, file.supersedes.clone()), (1, false, None));
+    }
+
+    // Totals over the day's chains only, storage as measured
+    let acme = &first["acme"].usage;
+    assert_eq!((acme.chains, acme.prompt_tokens, acme.completion_tokens), (3, 3300, 800));
+    assert_eq!(acme.by_model.iter().map(|model| model.model.as_str()).collect::<Vec<_>>(), vec![HAIKU, SONNET]);
+    assert_eq!(acme.cost_usd, (acme.by_model.iter().map(|model| model.cost_usd).sum::<f64>() * 1e6).round() / 1e6);
+    assert_eq!((acme.artifact_bytes, acme.memory_bytes), (20_480, 1_048_576));
+    let initech = &first["initech"].usage;
+    assert_eq!((initech.chains, initech.by_model.len(), initech.memory_bytes), (0, 0, 512));
+
+    // The hash covers the usage object, and the CSV leads with the totals
+    let hash = hex(&Sha256::digest(serde_json::to_vec(acme).unwrap()));
+    assert_eq!(first["acme"].content_hash, hash);
+    let csv = first["acme"].to_csv();
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines[0], CSV_HEADER.join(","));
+    assert_eq!(lines[1], format!("1,acme,2026-10-14,1,false,{},*,3300,800,{},3,20480,1048576", hash, acme.cost_usd));
+    assert!(lines[2].contains(&format!(",{},1300,700,", HAIKU)), "{}", lines[2]);
+    assert!(lines[2].ends_with(",,,"), "{}", lines[2]);
+    assert_eq!(lines.len(), 4);
+
+    // Delivered once, byte for byte what the day renders to
+    let report = exporter.run(next_morning()).await.unwrap();
+    assert_eq!((report.delivered.len(), report.unchanged, report.failed.len()), (3, 0, 0));
+    let stored = objects.get("acme/2026-10-14/usage-r1.json").await.unwrap().unwrap();
+    assert_eq!(stored, first["acme"].to_json());
+    let stored = objects.get("acme/2026-10-14/usage-r1.csv").await.unwrap().unwrap();
+    assert_eq!(stored, csv.as_bytes());
+
+    // Re-runs deliver nothing new
+    let report = exporter.run(next_morning() + Duration::hours(1)).await.unwrap();
+    assert_eq!((report.delivered.len(), report.unchanged), (0, 3));
+    assert_eq!(exporter.manifest(None, None, None).await.unwrap().len(), 3);
+    assert_eq!(objects.list("").await.unwrap().len(), 6);
+}
+
+#[tokio::test]
+async fn test_late_cost_records_amend_the_day() {
+    let dir = tempfile::tempdir().unwrap();
+    let objects = Arc::new(FsObjectStore::new(dir.path()));
+    let Fixture { usage, exporter, artifacts } = fixture(Destination::Store(objects.clone())).await;
+
+    // A chain started on the day whose cost was still being retried
+    let late = chain("acme", at(22), SONNET, 4000, 1000);
+    usage.record_chain_started(&late).await.unwrap();
+    exporter.run(next_morning()).await.unwrap();
+    let original = exporter.manifest(Some("acme"), Some(day()), Some(day())).await.unwrap();
+    assert_eq!(original.len(), 1);
+
+    // Storage measured since does not change the day
+    artifacts.set("acme", 99_999);
+    usage.record_chain(&late).await.unwrap();
+    let report = exporter.run(next_morning() + Duration::hours(1)).await.unwrap();
+    assert_eq!(report.delivered.len(), 1);
+    assert_eq!(report.unchanged, 2);
+
+    let amended = &report.delivered[0];
+    assert_eq!((amended.org_id.as_str(), amended.revision, amended.correction), ("acme", 2, true));
+    assert_eq!(amended.supersedes.as_deref(), Some(original[0].content_hash.as_str()));
+    assert!(amended.cost_usd > original[0].cost_usd);
+    assert_eq!(amended.keys, vec!["acme/2026-10-14/usage-r2.csv", "acme/2026-10-14/usage-r2.json"]);
+
+    let file: BillingFile =
+        serde_json::from_slice(&objects.get("acme/2026-10-14/usage-r2.json").await.unwrap().unwrap()).unwrap();
+    assert_eq!((file.correction, file.usage.chains, file.usage.prompt_tokens), (true, 4, 7300));
+    assert_eq!(file.usage.artifact_bytes, 20_480);
+    assert_eq!(file.content_hash, file.usage.content_hash());
+
+    // The original stays, and the manifest lists both revisions
+    assert!(objects.get("acme/2026-10-14/usage-r1.json").await.unwrap().is_some());
+    let manifest = exporter.manifest(Some("acme"), None, None).await.unwrap();
+    assert_eq!(manifest.iter().map(|file| (file.revision, file.correction)).collect::<Vec<_>>(), vec![(1, false), (2, true)]);
+
+    // The amended day renders to the amendment, and nothing more is sent
+    let files = by_org(exporter.files(day(), next_morning() + Duration::hours(2)).await.unwrap());
+    assert_eq!(files["acme"], file);
+    assert!(exporter.run(next_morning() + Duration::hours(2)).await.unwrap().delivered.is_empty());
+}
+
+/// Objects of a localstack-style S3 endpoint, which checks each request's
+/// signature headers and fails the first `failures` writes
+#[derive(Default)]
+struct MockS3 {
+    objects: Mutex<BTreeMap<String, Vec<u8>>>,
+    failures: AtomicUsize,
+}
+
+const ACCESS_KEY: &str = "test-key";
+
+async fn put_object(
+    State(s3): State<Arc<MockS3>>,
+    Path((bucket, key)): Path<(String, String)>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> (StatusCode, String) {
+    let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok()).unwrap_or_default().to_string();
+    let authorization = header("authorization");
+    if !authorization.starts_with(&format!("AWS4-HMAC-SHA256 Credential={}/", ACCESS_KEY))
+        || !authorization.contains("SignedHeaders=host;x-amz-content-sha256;x-amz-date")
+        || header("x-amz-content-sha256") != hex(&Sha256::digest(&body))
+        || header("x-amz-date").is_empty()
+    {
+        return (StatusCode::FORBIDDEN, "<Error><Code>SignatureDoesNotMatch</Code></Error>".to_string());
+    }
+    if s3.failures.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1)).is_ok() {
+        return (StatusCode::SERVICE_UNAVAILABLE, "<Error><Code>SlowDown</Code></Error>".to_string());
+    }
+    s3.objects.lock().insert(format!("{}/{}", bucket, key), body.to_vec());
+    (StatusCode::OK, String::new())
+}
+
+async fn get_object(State(s3): State<Arc<MockS3>>, Path((bucket, key)): Path<(String, String)>) -> (StatusCode, Vec<u8>) {
+    match s3.objects.lock().get(&format!("{}/{}", bucket, key)) {
+        Some(bytes) => (StatusCode::OK, bytes.clone()),
+        None => (StatusCode::NOT_FOUND, b"<Error><Code>NoSuchKey</Code></Error>".to_vec()),
+    }
+}
+
+async fn mock_s3() -> (Arc<MockS3>, String) {
+    let s3 = Arc::new(MockS3::default());
+    let app = Router::new().route("/:bucket/*key", put(put_object).get(get_object)).with_state(s3.clone());
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+    (s3, format!("http://{}", address))
+}
+
+#[tokio::test]
+async fn test_s3_delivery() {
+    let (s3, endpoint) = mock_s3().await;
+    let secret = SecretString::new("test-secret".to_string());
+    let store = S3ObjectStore::new(&endpoint, "finance", "us-east-1", "hal9/", ACCESS_KEY, secret).unwrap();
+    let Fixture { exporter, .. } = fixture(Destination::Store(Arc::new(store))).await;
+
+    // The endpoint throttles one write; that file waits for the next run
+    s3.failures.store(1, Ordering::SeqCst);
+    let report = exporter.run(next_morning()).await.unwrap();
+    assert_eq!((report.delivered.len(), report.failed.len()), (2, 1));
+    assert_eq!(report.failed, vec!["acme/2026-10-14/usage-r1.json"]);
+    assert!(report.delivered.iter().all(|file| file.destination == "s3"));
+    assert_eq!(exporter.manifest(None, None, None).await.unwrap().len(), 2);
+
+    let report = exporter.run(next_morning() + Duration::hours(1)).await.unwrap();
+    assert_eq!((report.delivered.len(), report.unchanged, report.failed.len()), (1, 2, 0));
+
+    let files = by_org(exporter.files(day(), next_morning()).await.unwrap());
+    let objects = s3.objects.lock().clone();
+    assert_eq!(objects.len(), 6);
+    for (org, file) in &files {
+        let json = &objects[&format!("finance/hal9/{}/2026-10-14/usage-r1.json", org)];
+        assert_eq!(json, &file.to_json(), "{}", org);
+        let csv = &objects[&format!("finance/hal9/{}/2026-10-14/usage-r1.csv", org)];
+        assert_eq!(csv, file.to_csv().as_bytes(), "{}", org);
+    }
+}
+
+#[test]
+fn test_validation() {
+    let auth = AuthConfig { enabled: true, ..Default::default() };
+    assert!(billing_export::validate(&config(), &auth).is_ok());
+    assert!(billing_export::validate(&config(), &AuthConfig::default()).is_err());
+    assert!(billing_export::validate(&BillingExportConfig { lookback_days: 0, ..config() }, &auth).is_err());
+
+    let webhook = |url: &str, secret: &str| BillingExportConfig {
+        destination: BillingDestination::Webhook { url: url.to_string(), secret: SecretString::new(secret.to_string()) },
+        ..config()
+    };
+    assert!(billing_export::validate(&webhook("https://billing.internal/hal9", "s3cret"), &auth).is_ok());
+    assert!(billing_export::validate(&webhook("https://billing.internal/hal9", ""), &auth).is_err());
+    assert!(billing_export::validate(&webhook("not a url", "s3cret"), &auth).is_err());
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
@@ -0,0 +1,182 @@
+//! Aligning and diffing the signal trees of two chains
+
+use chrono::Utc;
+
+use hal9_core::PropagationType;
+use hal9_server::chain_compare::{unified_diff, ChainComparison, NodeChange, NodeDiff};
+use hal9_server::chain_tracker::ChainStep;
+
+/// Step `id` of `neuron` under `parent`, producing `output`
+fn step(id: &str, parent: Option<&str>, neuron: &str, layer: &str, output: &str, tokens: u64) -> ChainStep {
+    ChainStep {
+        signal_id: id.to_string(),
+        parent_id: parent.map(str::to_string),
+        neuron_id: neuron.to_string(),
+        layer: layer.to_string(),
+        direction: PropagationType::Forward,
+        gradient: None,
+        model: Some("claude-3-haiku-20240307".to_string()),
+        output: Some(output.to_string()),
+        format: None,
+        citations: Vec::new(),
+        error: None,
+        duration_ms: 100,
+        prompt_tokens: tokens,
+        completion_tokens: tokens,
+        tags: Default::default(),
+        timestamp: Utc::now(),
+    }
+}
+
+fn compare(a: &[ChainStep], b: &[ChainStep]) -> ChainComparison {
+    ChainComparison::from_steps("a", a, "b", b, "claude-3-sonnet-20240229")
+}
+
+fn node<'a>(comparison: &'a ChainComparison, path: &str) -> &'a NodeDiff {
+    comparison.nodes.iter().find(|node| node.path == path).unwrap_or_else(|| panic!("no node at {}", path))
+}
+
+/// Signal ids paired at each node, in order
+fn pairs(comparison: &ChainComparison) -> Vec<(Option<&str>, Option<&str>)> {
+    comparison
+        .nodes
+        .iter()
+        .map(|node| (node.a.as_ref().map(|a| a.signal_id.as_str()), node.b.as_ref().map(|b| b.signal_id.as_str())))
+        .collect()
+}
+
+/// A strategy fanned out to an API design and a UI design, each with an
+/// implementation
+fn original() -> Vec<ChainStep> {
+    vec![
+        step("root", None, "strategist", "L4", "Build a todo app with an API and a UI", 100),
+        step("api", Some("root"), "architect", "L3", "REST API with list and create endpoints", 80),
+        step("ui", Some("root"), "designer", "L3", "Single page listing todos with a form", 80),
+        step("api-impl", Some("api"), "coder", "L2", "fn list() {}\nfn create() {}", 50),
+        step("ui-impl", Some("ui"), "coder", "L2", "<ul></ul>\n<form></form>", 50),
+    ]
+}
+
+#[test]
+fn test_identical_chains_are_unchanged() {
+    let comparison = compare(&original(), &original());
+
+    assert_eq!(comparison.nodes.len(), 5);
+    assert!(comparison.nodes.iter().all(|node| node.change == NodeChange::Unchanged && node.content_diff.is_none()));
+    assert_eq!(comparison.summary.nodes_unchanged, 5);
+    assert_eq!(comparison.summary.tokens_delta, 0);
+    assert_eq!(comparison.summary.cost_delta, 0.0);
+    let paths: Vec<&str> = comparison.nodes.iter().map(|node| node.path.as_str()).collect();
+    assert_eq!(paths, vec!["L4[0]", "L4[0]/L3[0]", "L4[0]/L3[0]/L2[0]", "L4[0]/L3[1]", "L4[0]/L3[1]/L2[0]"]);
+}
+
+#[test]
+fn test_reordered_siblings_align_by_content() {
+    // The replay processed the UI design first, under new signal ids
+    let replay = vec![
+        step("root2", None, "strategist", "L4", "Build a todo app with an API and a UI", 100),
+        step("ui2", Some("root2"), "designer", "L3", "Single page listing todos with a form", 80),
+        step("api2", Some("root2"), "architect", "L3", "REST API with list, create and delete endpoints", 90),
+        step("ui-impl2", Some("ui2"), "coder", "L2", "<ul></ul>\n<form></form>", 50),
+        step("api-impl2", Some("api2"), "coder", "L2", "fn list() {}\nfn create() {}\nfn delete() {}", 70),
+    ];
+    let comparison = compare(&original(), &replay);
+
+    assert_eq!(pairs(&comparison), vec![
+        (Some("root"), Some("root2")),
+        (Some("ui"), Some("ui2")),
+        (Some("ui-impl"), Some("ui-impl2")),
+        (Some("api"), Some("api2")),
+        (Some("api-impl"), Some("api-impl2")),
+    ]);
+    assert_eq!(node(&comparison, "L4[0]/L3[0]").change, NodeChange::Unchanged);
+
+    let api = node(&comparison, "L4[0]/L3[1]");
+    assert_eq!(api.change, NodeChange::Changed);
+    assert_eq!(api.tokens_delta, 20);
+    let impl_diff = node(&comparison, "L4[0]/L3[1]/L2[0]").content_diff.as_deref().unwrap();
+    assert_eq!(impl_diff, "--- a/api-impl\n+++ b/api-impl2\n@@ -1,2 +1,3 @@\n fn list() {}\n fn create() {}\n+fn delete() {}\n");
+
+    assert_eq!((comparison.summary.nodes_changed, comparison.summary.nodes_unchanged), (2, 3));
+    assert_eq!(comparison.summary.tokens_delta, 60);
+    assert!(comparison.summary.cost_delta > 0.0);
+}
+
+#[test]
+fn test_differing_fan_out_adds_and_removes_branches() {
+    // The replay dropped the UI branch and added a database design and a
+    // second implementation step under the API
+    let replay = vec![
+        step("root2", None, "strategist", "L4", "Build a todo app with an API", 100),
+        step("db2", Some("root2"), "dba", "L3", "Postgres schema with a todos table", 60),
+        step("api2", Some("root2"), "architect", "L3", "REST API with list and create endpoints", 80),
+        step("api-impl2", Some("api2"), "coder", "L2", "fn list() {}\nfn create() {}", 50),
+        step("api-tests2", Some("api2"), "tester", "L2", "#[test] fn lists() {}", 40),
+        step("db-impl2", Some("db2"), "coder", "L2", "CREATE TABLE todos ()", 30),
+    ];
+    let comparison = compare(&original(), &replay);
+
+    assert_eq!(pairs(&comparison), vec![
+        (Some("root"), Some("root2")),
+        (None, Some("db2")),
+        (None, Some("db-impl2")),
+        (Some("api"), Some("api2")),
+        (Some("api-impl"), Some("api-impl2")),
+        (None, Some("api-tests2")),
+        (Some("ui"), None),
+        (Some("ui-impl"), None),
+    ]);
+    assert_eq!(node(&comparison, "L4[0]").change, NodeChange::Changed);
+    assert_eq!(node(&comparison, "L4[0]/L3[1]/L2[0]").change, NodeChange::Unchanged);
+
+    let added = node(&comparison, "L4[0]/L3[1]/L2[1]");
+    assert_eq!(added.change, NodeChange::Added);
+    assert_eq!(added.content_diff.as_deref(), Some("--- /dev/null\n+++ b/api-tests2\n@@ -0,0 +1,1 @@\n+#[test] fn lists() {}\n"));
+    assert_eq!(added.tokens_delta, 80);
+
+    let removed = node(&comparison, "L4[0]/L3[2]/L2[0]");
+    assert_eq!(removed.change, NodeChange::Removed);
+    assert_eq!(removed.tokens_delta, -100);
+
+    let summary = &comparison.summary;
+    assert_eq!((summary.nodes_added, summary.nodes_removed, summary.nodes_changed, summary.nodes_unchanged), (3, 2, 1, 2));
+}
+
+#[test]
+fn test_same_neuron_siblings_pair_by_output() {
+    // Two signals to one neuron; the replay answered them in the other order
+    // and reworded one
+    let a = vec![
+        step("root", None, "strategist", "L4", "plan", 10),
+        step("x", Some("root"), "coder", "L2", "parse the config file and validate it", 10),
+        step("y", Some("root"), "coder", "L2", "render the report as html", 10),
+    ];
+    let b = vec![
+        step("root2", None, "strategist", "L4", "plan", 10),
+        step("y2", Some("root2"), "coder", "L2", "render the report as markdown", 10),
+        step("x2", Some("root2"), "coder", "L2", "parse the config file and validate it", 10),
+    ];
+    let comparison = compare(&a, &b);
+
+    assert_eq!(pairs(&comparison), vec![
+        (Some("root"), Some("root2")),
+        (Some("y"), Some("y2")),
+        (Some("x"), Some("x2")),
+    ]);
+    assert_eq!(node(&comparison, "L4[0]/L2[0]").change, NodeChange::Changed);
+    assert_eq!(node(&comparison, "L4[0]/L2[1]").change, NodeChange::Unchanged);
+}
+
+#[test]
+fn test_unified_diff_hunks() {
+    let old: String = (1..=20).map(|n| format!("line {}\n", n)).collect();
+    let new = old.replace("line 2\n", "line two\n").replace("line 18\n", "");
+
+    assert_eq!(unified_diff(&old, &new, "a", "b"), [
+        "--- a", "+++ b",
+        "@@ -1,5 +1,5 @@", " line 1", "-line 2", "+line two", " line 3", " line 4", " line 5",
+        "@@ -15,6 +15,5 @@", " line 15", " line 16", " line 17", "-line 18", " line 19", " line 20",
+        "",
+    ].join("\n"));
+    assert_eq!(unified_diff("same", "same", "a", "b"), "--- a\n+++ b\n");
+}
@@ -0,0 +1,85 @@
+//! Tests for per-user chain limits and fair scheduling
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use hal9_core::{config::ChainLimitsConfig, NeuronSignal};
+use hal9_server::{
+    chain_limits::{ChainLimiter, ChainOwner},
+    chain_tracker::ChainTracker,
+    fair_scheduler::FairScheduler,
+};
+
+fn owner(user_id: &str, role: &str) -> ChainOwner {
+    ChainOwner {
+        user_id: user_id.to_string(),
+        role: role.to_string(),
+        org_id: None,
+        api_key_id: None,
+    }
+}
+
+#[tokio::test]
+async fn test_light_user_latency_bounded_while_other_user_floods() {
+    let scheduler = Arc::new(FairScheduler::new(2));
+    let work = Duration::from_millis(20);
+
+    // Heavy user floods 50 signals
+    let mut flood = Vec::new();
+    for _ in 0..50 {
+        let scheduler = scheduler.clone();
+        flood.push(tokio::spawn(async move {
+            let _permit = scheduler.acquire("heavy").await;
+            tokio::time::sleep(work).await;
+        }));
+    }
+
+    // Let the flood saturate the pool and queue up
+    tokio::time::sleep(Duration::from_millis(10)).await;
+    assert_eq!(scheduler.stats().in_use, 2);
+
+    // Light user runs a 3-step chain behind the flood
+    let started = Instant::now();
+    for _ in 0..3 {
+        let _permit = scheduler.acquire("light").await;
+        tokio::time::sleep(work).await;
+    }
+    let light_latency = started.elapsed();
+
+    // FIFO would put the light user behind ~25 rounds (500ms+); round-robin
+    // costs at most one heavy step per light step.
+    assert!(
+        light_latency < Duration::from_millis(250),
+        "light user chain took {:?}",
+        light_latency
+    );
+
+    for handle in flood {
+        handle.await.unwrap();
+    }
+    assert_eq!(scheduler.stats().in_use, 0);
+}
+
+#[tokio::test]
+async fn test_flooding_user_refused_at_submission_while_others_admitted() {
+    let tracker = Arc::new(ChainTracker::new());
+    let limiter = ChainLimiter::new(ChainLimitsConfig::default(), tracker.clone());
+    let heavy = owner("heavy", "user");
+    let light = owner("light", "user");
+
+    let mut refused = 0;
+    for i in 0..50 {
+        let mut signal = NeuronSignal::forward("api", "n", "API", "L4", format!("task {}", i));
+        let chain_id = tracker.start(&mut signal);
+        if limiter.admit(&heavy, &chain_id).is_err() {
+            tracker.discard(&chain_id);
+            refused += 1;
+        }
+    }
+    assert_eq!(refused, 45);
+    assert_eq!(limiter.usage(&heavy, 0).active_chains, 5);
+
+    let mut signal = NeuronSignal::forward("api", "n", "API", "L4", "light task".into());
+    let chain_id = tracker.start(&mut signal);
+    assert!(limiter.admit(&light, &chain_id).is_ok());
+}
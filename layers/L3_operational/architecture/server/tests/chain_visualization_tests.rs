@@ -0,0 +1,170 @@
+//! Snapshot tests for chain visualizations. Run with `UPDATE_SNAPSHOTS=1` to
+//! rewrite the files in tests/snapshots after an intended rendering change.
+
+use std::path::Path;
+
+use chrono::Utc;
+
+use hal9_core::PropagationType::{self, Backward, Forward};
+use hal9_server::chain_tracker::{ChainStep, StepGradient};
+use hal9_server::chain_visualization::{ChainGraph, VisualizationFormat, MAX_NODES};
+
+fn assert_snapshot(name: &str, actual: &str) {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/snapshots").join(name);
+    if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+        std::fs::write(&path, actual).unwrap();
+        return;
+    }
+    let expected = std::fs::read_to_string(&path)
+        .unwrap_or_else(|e| panic!("missing snapshot {}: {}", path.display(), e));
+    assert_eq!(actual, expected, "{} changed; rerun with UPDATE_SNAPSHOTS=1 to accept", name);
+}
+
+struct Fixture<'a> {
+    id: &'a str,
+    parent: Option<&'a str>,
+    neuron: &'a str,
+    layer: &'a str,
+    direction: PropagationType,
+    duration_ms: i64,
+    tokens: u64,
+    error: Option<&'a str>,
+}
+
+fn steps(fixtures: &[Fixture]) -> Vec<ChainStep> {
+    fixtures
+        .iter()
+        .map(|f| ChainStep {
+            signal_id: f.id.to_string(),
+            parent_id: f.parent.map(str::to_string),
+            neuron_id: f.neuron.to_string(),
+            layer: f.layer.to_string(),
+            direction: f.direction,
+            gradient: (f.direction == Backward).then(|| StepGradient {
+                from_neuron: fixtures.iter().find(|p| Some(p.id) == f.parent).map_or("", |p| p.neuron).to_string(),
+                error_class: "timeout".to_string(),
+                magnitude: 1.0,
+                attenuation: Some(1.0),
+            }),
+            model: None,
+            output: f.error.is_none().then(|| "done".to_string()),
+            format: None,
+            citations: Vec::new(),
+            error: f.error.map(str::to_string),
+            duration_ms: f.duration_ms,
+            prompt_tokens: f.tokens / 2,
+            completion_tokens: f.tokens - f.tokens / 2,
+            tags: Default::default(),
+            timestamp: Utc::now(),
+        })
+        .collect()
+}
+
+/// A strategy fanned out to design and review, whose implementation step
+/// failed and sent an error back up
+fn review_chain() -> ChainGraph {
+    let fixtures = [
+        Fixture { id: "root", parent: None, neuron: "strategist", layer: "L4", direction: Forward, duration_ms: 1200, tokens: 300, error: None },
+        Fixture { id: "design", parent: Some("root"), neuron: "architect", layer: "L3", direction: Forward, duration_ms: 850, tokens: 220, error: None },
+        Fixture { id: "review", parent: Some("root"), neuron: "critic \"strict\"", layer: "L3", direction: Forward, duration_ms: 400, tokens: 90, error: None },
+        Fixture { id: "code", parent: Some("design"), neuron: "coder", layer: "L2", direction: Forward, duration_ms: 30_000, tokens: 0, error: Some("timeout") },
+        Fixture { id: "retry", parent: Some("code"), neuron: "architect", layer: "L3", direction: Backward, duration_ms: 15, tokens: 40, error: None },
+    ];
+    ChainGraph::from_steps("chain-review", &steps(&fixtures), MAX_NODES)
+}
+
+/// A root fanning out to twelve workers, cut off at five nodes
+fn wide_chain() -> ChainGraph {
+    let workers = [
+        "worker-01", "worker-02", "worker-03", "worker-04", "worker-05", "worker-06",
+        "worker-07", "worker-08", "worker-09", "worker-10", "worker-11", "worker-12",
+    ];
+    let mut fixtures = vec![Fixture {
+        id: "root", parent: None, neuron: "dispatcher", layer: "L4", direction: Forward, duration_ms: 10, tokens: 10, error: None,
+    }];
+    fixtures.extend(workers.iter().map(|worker| Fixture {
+        id: worker, parent: Some("root"), neuron: worker, layer: "L2", direction: Forward, duration_ms: 5, tokens: 5, error: None,
+    }));
+    ChainGraph::from_steps("chain-wide", &steps(&fixtures), 5)
+}
+
+#[test]
+fn test_tree_is_built_breadth_first_from_parents() {
+    let graph = review_chain();
+    assert_eq!(graph.truncated, 0);
+    let order: Vec<_> = graph.nodes.iter().map(|n| (n.signal_id.as_str(), n.depth)).collect();
+    assert_eq!(order, [("root", 0), ("design", 1), ("review", 1), ("code", 2), ("retry", 3)]);
+    assert_eq!(graph.edges.len(), 4);
+    assert_eq!(graph.edges[3].direction, Backward);
+}
+
+#[test]
+fn test_feedback_renders_both_directions() {
+    let graph = review_chain();
+    let feedback = &graph.edges[3];
+    assert_eq!(feedback.error_class.as_deref(), Some("timeout"));
+    assert!(graph.edges[..3].iter().all(|e| e.direction == Forward && e.error_class.is_none()));
+
+    let mermaid = graph.to_mermaid();
+    assert!(mermaid.contains("n0 -->|forward| n1"));
+    assert!(mermaid.contains("n3 -.->|backward: timeout| n4"));
+    assert!(mermaid.contains("linkStyle 3 "));
+    let svg = graph.to_svg();
+    assert_eq!(svg.matches("url(#arrow)").count(), 3);
+    assert_eq!(svg.matches("url(#arrow-backward)").count(), 1);
+}
+
+#[test]
+fn test_review_chain_svg() {
+    assert_snapshot("review_chain.svg", &review_chain().render(VisualizationFormat::Svg));
+}
+
+#[test]
+fn test_review_chain_mermaid() {
+    assert_snapshot("review_chain.mmd", &review_chain().render(VisualizationFormat::Mermaid));
+}
+
+#[test]
+fn test_review_chain_dot() {
+    assert_snapshot("review_chain.dot", &review_chain().render(VisualizationFormat::Dot));
+}
+
+#[test]
+fn test_truncated_chain_svg() {
+    assert_snapshot("wide_chain.svg", &wide_chain().render(VisualizationFormat::Svg));
+}
+
+#[test]
+fn test_truncated_chain_mermaid() {
+    assert_snapshot("wide_chain.mmd", &wide_chain().render(VisualizationFormat::Mermaid));
+}
+
+#[test]
+fn test_truncated_chain_dot() {
+    assert_snapshot("wide_chain.dot", &wide_chain().render(VisualizationFormat::Dot));
+}
+
+#[test]
+fn test_large_chain_stays_small() {
+    // Every signal spawns four children
+    let ids: Vec<String> = (0..5_000).map(|i| format!("s{}", i)).collect();
+    let fixtures: Vec<Fixture> = (0..ids.len())
+        .map(|i| Fixture {
+            id: &ids[i],
+            parent: (i > 0).then(|| ids[(i - 1) / 4].as_str()),
+            neuron: "worker",
+            layer: "L2",
+            direction: Forward,
+            duration_ms: 1,
+            tokens: 1,
+            error: None,
+        })
+        .collect();
+    let graph = ChainGraph::from_steps("chain-large", &steps(&fixtures), MAX_NODES);
+
+    assert_eq!(graph.nodes.len(), MAX_NODES);
+    assert_eq!(graph.truncated, 5_000 - MAX_NODES);
+    let svg = graph.to_svg();
+    assert!(svg.len() < 200 * 1024, "svg is {} bytes", svg.len());
+    assert!(svg.contains("4800 more signals not shown"));
+}
@@ -0,0 +1,237 @@
+//! Chaos injection: chains riding out injected failures through error
+//! recovery, each kind of fault, and the admin API. Run nightly in release
+//! mode with `--features chaos`.
+
+mod common;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::http::StatusCode;
+use serde_json::{json, Value};
+
+use hal9_core::{NeuronSignal, ServerConfig};
+use hal9_server::api::create_api_router;
+use hal9_server::chaos::assertions::{self, ChaosRun, NIGHTLY};
+use hal9_server::chaos::{ChaosEngine, ChaosRequest, Fault};
+use hal9_server::network::{TcpTransport, TransportConfig};
+use hal9_server::server::HAL9Server;
+use hal9_server::simulation::SimRng;
+
+use common::call;
+
+/// Chains per batch; enough that 95% is a meaningful share
+const CHAINS: usize = 100;
+/// Chains in flight at once
+const CONCURRENCY: usize = 10;
+const CHAIN_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// L4 plans, L3 designs and L2 builds; failed L3 signals are retried
+fn config() -> ServerConfig {
+    common::mock_config(json!({
+        "server_id": "chaos-test",
+        "neurons": [
+            {"id": "planner", "layer": "L4", "forward_connections": ["designer"], "backward_connections": []},
+            {"id": "designer", "layer": "L3", "forward_connections": ["coder"], "backward_connections": ["planner"]},
+            {"id": "coder", "layer": "L2", "forward_connections": [], "backward_connections": ["designer"]},
+        ],
+        "claude": {
+            "mock_responses": {
+                "L4": [{"trigger": "default", "response": "FORWARD_TO: designer\nCONTENT: Design the checkout", "delay_ms": 20}],
+                "L3": [{"trigger": "default", "response": "FORWARD_TO: coder\nCONTENT: Build the cart", "delay_ms": 20}],
+                "L2": [{"trigger": "default", "response": "RESULT: Cart built", "delay_ms": 20}],
+            },
+        },
+        "recovery": {
+            "L3": {"steps": [{"type": "retry", "attempts": 3, "initial_backoff_ms": 10, "max_backoff_ms": 50}]},
+        },
+        "timeouts": {"layers_ms": {"L4": 200}},
+        "memory": {"enabled": false},
+    }))
+}
+
+async fn started() -> Arc<HAL9Server> {
+    let server = Arc::new(HAL9Server::new(config()));
+    server.start().await.unwrap();
+    server
+}
+
+/// Distinct tasks, so L4 answers each chain itself rather than from cache
+fn tasks(batch: &str, count: usize) -> Vec<NeuronSignal> {
+    (0..count)
+        .map(|i| NeuronSignal::forward("api-client", "planner", "API", "L4", format!("{} task {}", batch, i)))
+        .collect()
+}
+
+async fn run(server: &HAL9Server, batch: &str, count: usize) -> ChaosRun {
+    assertions::run_chains(server, tasks(batch, count), CONCURRENCY, CHAIN_TIMEOUT).await.unwrap()
+}
+
+fn request(body: Value) -> ChaosRequest {
+    serde_json::from_value(body).unwrap()
+}
+
+#[tokio::test]
+async fn test_chains_recover_from_l3_failures() {
+    let server = started().await;
+    let baseline = run(&server, "baseline", CHAINS).await;
+
+    // One in five L3 signals dies mid-processing, retries included
+    let injection = server
+        .inject_chaos(request(json!({"kind": "kill_neuron", "target": "L3", "rate": 0.2, "duration_secs": 300})), None)
+        .unwrap();
+    let chaos = run(&server, "chaos", CHAINS).await;
+
+    assertions::assert_resilient(&baseline, &chaos, NIGHTLY);
+    let hits = server.chaos_status().injections[0].hits;
+    assert!(hits > 0, "no L3 signal was killed");
+
+    // Without recovery a killed signal fails its chain
+    assert_eq!(server.stop_chaos(injection.id, None).unwrap().hits, hits);
+    server
+        .inject_chaos(request(json!({"kind": "kill_neuron", "target": "coder", "duration_secs": 300})), None)
+        .unwrap();
+    let killed = run(&server, "unrecovered", 5).await;
+    assert_eq!(killed.completion_rate(), 0.0);
+    assert!(killed.errors().iter().all(|e| e.contains("killed mid-signal")), "{:?}", killed.errors());
+    assert_eq!(server.clear_chaos(None), 1);
+    assertions::assert_completion_rate(&run(&server, "cleared", 5).await, 1.0);
+}
+
+#[tokio::test]
+async fn test_layer_delay_and_pool_exhaustion() {
+    let server = started().await;
+
+    server
+        .inject_chaos(request(json!({"kind": "delay_layer", "delay_ms": 300, "target": "L2", "duration_secs": 60})), None)
+        .unwrap();
+    let delayed = run(&server, "delayed", 3).await;
+    assertions::assert_completion_rate(&delayed, 1.0);
+    assert!(delayed.percentile_ms(0.0).unwrap() >= 300, "{:?}", delayed.percentile_ms(0.0));
+    assert_eq!(server.chaos_status().injections[0].hits, 3);
+    server.clear_chaos(None);
+
+    // Calls wait for a connection until L4's 200ms timeout gives up on them.
+    // Only L4 is sure to call Claude: every chain sends the lower layers
+    // the same prompt, which they answer from cache after the first.
+    server
+        .inject_chaos(request(json!({"kind": "exhaust_claude_pool", "target": "planner", "duration_secs": 60})), None)
+        .unwrap();
+    let exhausted = run(&server, "exhausted", 3).await;
+    assert_eq!(exhausted.completion_rate(), 0.0);
+    assert!(exhausted.errors().iter().all(|e| e.contains("timeout")), "{:?}", exhausted.errors());
+    server.clear_chaos(None);
+    assertions::assert_completion_rate(&run(&server, "recovered", 3).await, 1.0);
+}
+
+#[tokio::test]
+async fn test_dropped_messages_and_corrupted_plugins() {
+    let config = TransportConfig {
+        bind_address: "127.0.0.1:0".parse().unwrap(),
+        ..Default::default()
+    };
+    let mut receiver = TcpTransport::new(config.clone(), "server-b".to_string());
+    receiver.start().await.unwrap();
+    let mut signals = receiver.signal_receiver().await.unwrap();
+
+    let engine = Arc::new(ChaosEngine::new(SimRng::seeded(7)));
+    let mut sender = TcpTransport::new(config, "server-a".to_string());
+    sender.set_chaos(engine.clone());
+    sender.start().await.unwrap();
+    sender.connect(receiver.local_addr().unwrap(), "server-b").await.unwrap();
+
+    let signal = |content: &str| NeuronSignal::forward("n-a", "n-b", "L4", "L3", content.to_string());
+    let injection = engine
+        .inject(request(json!({"kind": "drop_messages", "target": "server-b", "duration_secs": 60})), Some("ops"))
+        .unwrap();
+    assert_eq!(injection.created_by.as_deref(), Some("ops"));
+    sender.send_signal("server-b", signal("lost")).await.unwrap();
+    engine.stop(injection.id, Some("ops")).unwrap();
+    sender.send_signal("server-b", signal("delivered")).await.unwrap();
+
+    let (_, received) = tokio::time::timeout(Duration::from_secs(2), signals.recv()).await.unwrap().unwrap();
+    assert_eq!(received.payload.activation.content, "delivered");
+    assert!(!engine.drops_message("server-b"));
+
+    // Another peer's messages are untouched; a partial rate drops some
+    engine
+        .inject(request(json!({"kind": "drop_messages", "target": "server-c", "rate": 0.5, "duration_secs": 60})), None)
+        .unwrap();
+    assert!(!engine.drops_message("server-b"));
+    let dropped = (0..200).filter(|_| engine.drops_message("server-c")).count();
+    assert!((60..140).contains(&dropped), "{} of 200 dropped", dropped);
+
+    engine
+        .inject(request(json!({"kind": "corrupt_plugin", "target": "formatter", "duration_secs": 60})), None)
+        .unwrap();
+    let corrupted = engine.corrupt_plugin_response("formatter", "{\"status\": \"ok\"}").unwrap();
+    assert_eq!(corrupted, "{\"status\u{fffd}\u{fffd}\u{fffd}");
+    assert_eq!(engine.corrupt_plugin_response("linter", "{}"), None);
+}
+
+#[tokio::test]
+async fn test_admin_api_and_expiry() {
+    let server = started().await;
+    let app = create_api_router(server.clone());
+
+    let (status, body) = call(&app, "GET", "/api/v1/admin/chaos", None).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["data"], json!({"available": true, "injections": []}));
+
+    for invalid in [
+        json!({"kind": "kill_neuron", "rate": 0.0, "duration_secs": 60}),
+        json!({"kind": "kill_neuron", "duration_secs": 0}),
+        json!({"kind": "kill_neuron", "duration_secs": 86_400}),
+        json!({"kind": "delay_layer", "delay_ms": 0, "duration_secs": 60}),
+    ] {
+        let (status, body) = call(&app, "POST", "/api/v1/admin/chaos", Some(invalid.clone())).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{} -> {}", invalid, body);
+    }
+
+    let (status, body) = call(
+        &app,
+        "POST",
+        "/api/v1/admin/chaos",
+        Some(json!({"kind": "delay_layer", "delay_ms": 50, "target": "L4", "duration_secs": 60})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{}", body);
+    assert_eq!((body["data"]["kind"].as_str(), body["data"]["delay_ms"].as_u64()), (Some("delay_layer"), Some(50)));
+    assert_eq!(body["data"]["rate"], 1.0);
+    let id = body["data"]["id"].as_str().unwrap().to_string();
+
+    let (_, body) = call(&app, "GET", "/api/v1/admin/chaos", None).await;
+    assert_eq!(body["data"]["injections"][0]["id"], id.as_str());
+    let (status, _) = call(&app, "DELETE", &format!("/api/v1/admin/chaos/{}", id), None).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = call(&app, "DELETE", &format!("/api/v1/admin/chaos/{}", id), None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, _) = call(&app, "DELETE", "/api/v1/admin/chaos/not-a-uuid", None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    // An injection lifts itself once its duration is up
+    server
+        .inject_chaos(request(json!({"kind": "kill_neuron", "target": "planner", "duration_secs": 1})), None)
+        .unwrap();
+    assert_eq!(run(&server, "expiring", 1).await.completion_rate(), 0.0);
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+    assert!(server.chaos_status().injections.is_empty());
+    assertions::assert_completion_rate(&run(&server, "expired", 1).await, 1.0);
+
+    server
+        .inject_chaos(request(json!({"kind": "drop_messages", "duration_secs": 60})), None)
+        .unwrap();
+    let (status, body) = call(&app, "DELETE", "/api/v1/admin/chaos", None).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["data"]["stopped"], 1);
+}
+
+#[test]
+fn test_fault_kinds_round_trip() {
+    let request = request(json!({"kind": "exhaust_claude_pool", "duration_secs": 30}));
+    assert_eq!((request.fault, request.target, request.rate), (Fault::ExhaustClaudePool, None, 1.0));
+    let delay: ChaosRequest = serde_json::from_value(json!({"kind": "delay_layer", "delay_ms": 5, "duration_secs": 30})).unwrap();
+    assert_eq!(delay.fault, Fault::DelayLayer { delay_ms: 5 });
+    assert!(serde_json::from_value::<ChaosRequest>(json!({"kind": "meteor", "duration_secs": 30})).is_err());
+    assert!(serde_json::from_value::<ChaosRequest>(json!({"kind": "kill_neuron"})).is_err());
+}
@@ -1,9 +1,12 @@
 //! Circuit breaker unit tests
+//!
+//! The breaker reads tokio's clock, so timeouts and windows elapse on paused
+//! time instead of real sleeps.
 
 use hal9_server::circuit_breaker::{CircuitBreaker, CircuitBreakerConfig, CircuitState};
 use std::time::Duration;
 
-#[tokio::test]
+#[tokio::test(start_paused = true)]
 async fn test_circuit_breaker_state_transitions() {
     let config = CircuitBreakerConfig {
         failure_threshold: 3,
@@ -47,7 +50,7 @@ async fn test_circuit_breaker_state_transitions() {
     assert_eq!(cb.state().await, CircuitState::Closed);
 }
 
-#[tokio::test]
+#[tokio::test(start_paused = true)]
 async fn test_circuit_breaker_failure_in_half_open() {
     let config = CircuitBreakerConfig {
         failure_threshold: 1,
@@ -75,7 +78,7 @@ async fn test_circuit_breaker_failure_in_half_open() {
     assert!(!cb.allow_request().await);
 }
 
-#[tokio::test]
+#[tokio::test(start_paused = true)]
 async fn test_circuit_breaker_window_expiry() {
     let config = CircuitBreakerConfig {
         failure_threshold: 3,
@@ -0,0 +1,158 @@
+//! Citations of injected memory: trailer parsing, citation counts and
+//! provenance on child signals
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use chrono::{Duration, Utc};
+use hal9_core::config::{get_system_prompt, MemoryNamespacesConfig, CITATION_INSTRUCTIONS};
+use hal9_core::memory::{MemoryBuilder, MemoryEntry, MemoryStore, MemoryType, NamespacedMemory, SqliteMemoryStore};
+use hal9_core::{NeuronConfig, NeuronInterface, NeuronSignal};
+use hal9_server::chain_tracker::ChainTracker;
+use hal9_server::citations::{self, reference_of, CitationTrailer, CITED_KEY};
+use hal9_server::{ManagedNeuron, MockClaude};
+use uuid::Uuid;
+
+const FIRST: &str = "mem-1a2b3c4d";
+const SECOND: &str = "mem-5e6f7a8b";
+
+#[test]
+fn test_trailer_formats() {
+    let cases: [(&str, &[&str]); 8] = [
+        ("CITATIONS: mem-1a2b3c4d, mem-5e6f7a8b", &[FIRST, SECOND]),
+        ("Citations: [mem-1a2b3c4d] [mem-5e6f7a8b]", &[FIRST, SECOND]),
+        ("CITATIONS: [\"mem-1a2b3c4d\", \"mem-5e6f7a8b\"]", &[FIRST, SECOND]),
+        ("**Citations:** MEM-1A2B3C4D; mem-5e6f7a8b.", &[FIRST, SECOND]),
+        ("CITATIONS:\n- mem-1a2b3c4d\n- mem-5e6f7a8b\n", &[FIRST, SECOND]),
+        ("CITATIONS: [\n  \"mem-1a2b3c4d\",\n  \"mem-1a2b3c4d\"\n]", &[FIRST]),
+        ("cited: 1. mem-5e6f7a8b 2. mem-1a2b3c4d", &[SECOND, FIRST]),
+        ("CITATIONS: none", &[]),
+    ];
+    for (trailer, expected) in cases {
+        let response = format!("FORWARD_TO: coder\nCONTENT: Build the parser\n\n{}", trailer);
+        let parsed = CitationTrailer::parse(&response).unwrap_or_else(|| panic!("no trailer in {:?}", trailer));
+        assert_eq!(parsed.body, "FORWARD_TO: coder\nCONTENT: Build the parser", "{:?}", trailer);
+        assert_eq!(parsed.references, expected, "{:?}", trailer);
+    }
+
+    // Ids too short or run into other text are not references
+    let parsed = CitationTrailer::parse("Done.\nCITATIONS: mem-1a2b, mem-1a2b3c4d5, xmem-5e6f7a8b").unwrap();
+    assert_eq!(parsed.body, "Done.");
+    assert!(parsed.references.is_empty());
+
+    // Only the end of a response is a trailer
+    assert!(CitationTrailer::parse("CITATIONS: mem-1a2b3c4d\nThen more prose").is_none());
+    assert!(CitationTrailer::parse("No citations here").is_none());
+    assert!(CitationTrailer::parse("Done.\n## Citations\n- mem-1a2b3c4d").is_none());
+    let parsed = CitationTrailer::parse("CITATIONS: mem-1a2b3c4d\nbody\nCitation: mem-5e6f7a8b").unwrap();
+    assert_eq!(parsed.body, "CITATIONS: mem-1a2b3c4d\nbody");
+    assert_eq!(parsed.references, [SECOND]);
+}
+
+#[test]
+fn test_references_resolve_to_injected_entries() {
+    let entry = MemoryBuilder::new("planner".to_string(), "L4".to_string())
+        .with_content("Split work by layer".to_string())
+        .build();
+    let reference = reference_of(&entry.id);
+    assert!(reference.starts_with("mem-") && reference.len() == 12);
+    assert!(entry.id.simple().to_string().starts_with(&reference[4..]));
+
+    let injected = vec![citations::injected(&entry)];
+    let cited = citations::resolve(&[FIRST.to_string(), reference.clone()], &injected);
+    assert_eq!(cited.len(), 1);
+    assert_eq!(cited[0].memory_id, entry.id.to_string());
+
+    let mut metadata = HashMap::new();
+    citations::apply_to(&mut metadata, &[]);
+    assert!(metadata.is_empty());
+    citations::apply_to(&mut metadata, &cited);
+    assert_eq!(metadata[CITED_KEY], entry.id.to_string());
+}
+
+async fn store() -> Arc<SqliteMemoryStore> {
+    let store = SqliteMemoryStore::in_memory().await.unwrap();
+    store.initialize().await.unwrap();
+    Arc::new(store)
+}
+
+fn task(neuron_id: &str, content: &str) -> MemoryEntry {
+    MemoryBuilder::new(neuron_id.to_string(), "L4".to_string())
+        .with_type(MemoryType::Task)
+        .with_content(content.to_string())
+        .with_importance(0.1)
+        .build()
+}
+
+#[tokio::test]
+async fn test_citation_counter_increments() {
+    let store = store().await;
+    let cited = store.store(task("planner", "Plan the release")).await.unwrap();
+    let ignored = store.store(task("planner", "Plan the offsite")).await.unwrap();
+
+    store.record_citation(cited).await.unwrap();
+    store.record_citation(cited).await.unwrap();
+    assert_eq!(store.get(cited).await.unwrap().unwrap().citations, 2);
+    assert_eq!(store.get(ignored).await.unwrap().unwrap().citations, 0);
+    // Unknown entries are not an error
+    store.record_citation(Uuid::new_v4()).await.unwrap();
+
+    // Cleanup keeps old unimportant entries that were cited
+    let deleted = store.cleanup(Utc::now() + Duration::days(1), 0.5).await.unwrap();
+    assert_eq!(deleted, 1);
+    assert!(store.get(cited).await.unwrap().is_some());
+    assert!(store.get(ignored).await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn test_cited_memories_tracked_through_a_chain() {
+    let store = store().await;
+    let recalled = task("planner", "Ship the parser first");
+    let reference = reference_of(&recalled.id);
+    store.store(recalled.clone()).await.unwrap();
+
+    let config = NeuronConfig {
+        id: "planner".to_string(),
+        layer: "L4".to_string(),
+        claude_command: "claude".to_string(),
+        system_prompt: None,
+        forward_connections: vec!["coder".to_string()],
+        backward_connections: vec![],
+        settings: HashMap::new(),
+    };
+    // The model cites the recalled entry and one that was never recalled
+    let response = format!("FORWARD_TO: coder\nCONTENT: Build the parser\nCITATIONS: {}, {}", reference, SECOND);
+    let claude = MockClaude::scripted("L4", vec![response]);
+    let mut neuron = ManagedNeuron::new(config, Box::new(claude)).unwrap();
+    neuron.set_memory(Arc::new(NamespacedMemory::new(store.clone(), MemoryNamespacesConfig::default())));
+
+    let tracker = ChainTracker::new();
+    let mut signal = NeuronSignal::forward("api", "planner", "API", "L4", "Plan the work".to_string());
+    let chain_id = tracker.start(&mut signal);
+
+    let prompt = neuron.recovery_prompt(&signal).await;
+    assert!(prompt.contains(&format!("- [{}] Ship the parser first\n", reference)), "{}", prompt);
+    assert!(get_system_prompt("L4").ends_with(CITATION_INSTRUCTIONS));
+
+    let output = neuron.process_signal(&signal).await.unwrap();
+    assert_eq!(output, "FORWARD_TO: coder\nCONTENT: Build the parser");
+    assert_eq!(store.get(recalled.id).await.unwrap().unwrap().citations, 1);
+
+    let children = neuron.parse_response(&output, &signal);
+    assert_eq!(children.len(), 1);
+    assert_eq!(children[0].metadata[CITED_KEY], recalled.id.to_string());
+    // Grandchildren carry their own parent's citations, not these
+    let grandchildren = neuron.parse_response(&output, &children[0]);
+    assert!(grandchildren.iter().all(|child| !child.metadata.contains_key(CITED_KEY)));
+
+    let cited = neuron.take_citations(&signal.signal_id).unwrap();
+    tracker.record_citations(&signal, cited);
+    tracker.record_step(&signal, Ok(output.as_str()), 0);
+    let result = tracker.aggregate(&chain_id).unwrap();
+    assert_eq!(result.cited_memories.len(), 1);
+    let node = &result.cited_memories[0];
+    assert_eq!((node.neuron_id.as_str(), node.layer.as_str()), ("planner", "L4"));
+    assert_eq!(node.memories[0].reference, reference);
+    assert_eq!(node.memories[0].memory_id, recalled.id.to_string());
+    assert!(neuron.take_citations(&signal.signal_id).is_none());
+}
@@ -0,0 +1,162 @@
+//! Codegen job progress streamed over a WebSocket
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures_util::StreamExt;
+use tokio::net::{TcpListener, TcpStream};
+use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+use tokio_tungstenite::tungstenite::{self, Message};
+use tokio_tungstenite::{connect_async, MaybeTlsStream, WebSocketStream};
+
+use hal9_server::api_codegen::job_routes;
+use hal9_server::codegen_jobs::{CodegenEvent, CodegenEventKind, CodegenJob, CodegenJobs, ManifestFile};
+
+type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+async fn serve(jobs: Arc<CodegenJobs>) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, job_routes(jobs)).await.unwrap();
+    });
+    format!("{}", addr)
+}
+
+/// The events a scripted job emits, in order
+fn script() -> Vec<CodegenEventKind> {
+    vec![
+        CodegenEventKind::Stage { stage: "architecture".to_string(), progress: 10 },
+        CodegenEventKind::FileCompleted { path: "Cargo.toml".to_string(), bytes: 312 },
+        CodegenEventKind::Warning { message: "No database chosen, skipping migrations".to_string() },
+        CodegenEventKind::Stage { stage: "backend".to_string(), progress: 60 },
+        CodegenEventKind::FileCompleted { path: "src/main.rs".to_string(), bytes: 2048 },
+    ]
+}
+
+/// Emit `events` a few milliseconds apart, then complete the job
+fn run_script(job: Arc<CodegenJob>, events: Vec<CodegenEventKind>) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        for event in events {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            job.emit(event).unwrap();
+        }
+        job.complete().unwrap();
+    })
+}
+
+async fn connect(addr: &str, job_id: &str, from_seq: Option<u64>) -> Result<Socket, tungstenite::Error> {
+    let query = from_seq.map(|seq| format!("?from_seq={}", seq)).unwrap_or_default();
+    let url = format!("ws://{}/api/v1/codegen/jobs/{}/stream{}", addr, job_id, query);
+    connect_async(url).await.map(|(socket, _)| socket)
+}
+
+async fn next_event(socket: &mut Socket) -> CodegenEvent {
+    let message = tokio::time::timeout(Duration::from_secs(2), socket.next())
+        .await
+        .expect("event not received")
+        .unwrap()
+        .unwrap();
+    match message {
+        Message::Text(text) => serde_json::from_str(&text).unwrap(),
+        other => panic!("expected an event, got {:?}", other),
+    }
+}
+
+/// Read events until the server closes the socket
+async fn read_to_close(socket: &mut Socket) -> Vec<CodegenEvent> {
+    let mut events = Vec::new();
+    loop {
+        let message = tokio::time::timeout(Duration::from_secs(2), socket.next())
+            .await
+            .expect("socket not closed");
+        match message {
+            Some(Ok(Message::Text(text))) => events.push(serde_json::from_str(&text).unwrap()),
+            Some(Ok(Message::Close(frame))) => {
+                assert_eq!(frame.unwrap().code, CloseCode::Normal);
+                return events;
+            }
+            other => panic!("expected an event or close, got {:?}", other),
+        }
+    }
+}
+
+fn seqs(events: &[CodegenEvent]) -> Vec<u64> {
+    events.iter().map(|event| event.seq).collect()
+}
+
+#[tokio::test]
+async fn test_events_stream_in_order_and_close_after_terminal() {
+    let jobs = Arc::new(CodegenJobs::default());
+    let addr = serve(jobs.clone()).await;
+    let job = jobs.create("job-1", "/projects/job-1");
+    job.stage("queued", 0).unwrap();
+
+    // One event from before the client connected, the rest live
+    let mut socket = connect(&addr, "job-1", None).await.unwrap();
+    run_script(job.clone(), script()).await.unwrap();
+    let events = read_to_close(&mut socket).await;
+
+    assert_eq!(seqs(&events), (1..=7).collect::<Vec<_>>());
+    let kinds: Vec<_> = events.iter().map(|event| event.kind.clone()).collect();
+    assert_eq!(kinds[1..6], script()[..]);
+    match &events[6].kind {
+        CodegenEventKind::Completed { manifest } => {
+            assert_eq!(manifest.files, vec![
+                ManifestFile { path: "Cargo.toml".to_string(), bytes: 312 },
+                ManifestFile { path: "src/main.rs".to_string(), bytes: 2048 },
+            ]);
+            assert_eq!(manifest.total_bytes, 2360);
+        }
+        other => panic!("expected the manifest, got {:?}", other),
+    }
+    assert!(socket.next().await.is_none_or(|message| message.is_err()));
+
+    // Pollers see the same outcome
+    let status = job.status();
+    assert_eq!((status.status.as_str(), status.progress, status.files_generated, status.last_seq), ("completed", 100, 2, 7));
+    assert!(job.warning("too late").is_err());
+}
+
+#[tokio::test]
+async fn test_reconnect_resumes_from_seq() {
+    let jobs = Arc::new(CodegenJobs::default());
+    let addr = serve(jobs.clone()).await;
+    let job = jobs.create("job-2", "/projects/job-2");
+    let script = script();
+    for event in &script[..3] {
+        job.emit(event.clone()).unwrap();
+    }
+
+    let mut socket = connect(&addr, "job-2", None).await.unwrap();
+    let seen = [next_event(&mut socket).await, next_event(&mut socket).await];
+    assert_eq!(seqs(&seen), vec![1, 2]);
+    drop(socket);
+
+    // The job carries on while the client is away
+    run_script(job.clone(), script[3..].to_vec()).await.unwrap();
+
+    let mut socket = connect(&addr, "job-2", Some(3)).await.unwrap();
+    let events = read_to_close(&mut socket).await;
+    assert_eq!(seqs(&events), vec![3, 4, 5, 6]);
+    assert_eq!(events[0].kind, script[2]);
+    assert!(events[3].kind.is_terminal());
+
+    // A client that already saw the terminal event is closed at once
+    let mut socket = connect(&addr, "job-2", Some(7)).await.unwrap();
+    assert!(read_to_close(&mut socket).await.is_empty());
+}
+
+#[tokio::test]
+async fn test_stream_rejects_unknown_jobs_and_future_seqs() {
+    let jobs = Arc::new(CodegenJobs::default());
+    let addr = serve(jobs.clone()).await;
+    jobs.create("job-3", "/projects/job-3").stage("queued", 0).unwrap();
+
+    let status = |result: Result<Socket, tungstenite::Error>| match result {
+        Err(tungstenite::Error::Http(response)) => response.status().as_u16(),
+        other => panic!("expected an HTTP error, got {:?}", other.map(|_| ())),
+    };
+    assert_eq!(status(connect(&addr, "missing", None).await), 404);
+    assert_eq!(status(connect(&addr, "job-3", Some(5)).await), 400);
+}
@@ -0,0 +1,161 @@
+//! Helpers shared by the server integration tests: configs for servers
+//! answered by mock Claude, neurons answered by a scripted provider, and
+//! requests sent straight through a router
+
+#![allow(dead_code)]
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use axum::{
+    body::Body,
+    http::{header, HeaderMap, HeaderName, Request, StatusCode},
+    Router,
+};
+use http_body_util::BodyExt;
+use serde_json::{json, Value};
+use tokio::sync::mpsc;
+use tower::ServiceExt;
+
+use hal9_core::{Error, NeuronConfig, Result, ServerConfig};
+use hal9_server::claude::{ClaudeInterface, TokenUsage};
+
+/// A server without neurons answered by mock Claude, with `overrides`
+/// merged over it: objects key by key, anything else replaced
+pub fn mock_config(overrides: Value) -> ServerConfig {
+    let mut config = json!({
+        "server_id": "test",
+        "neurons": [],
+        "claude": {"mode": "mock"},
+    });
+    merge(&mut config, overrides);
+    serde_json::from_value(config).unwrap()
+}
+
+fn merge(base: &mut Value, overrides: Value) {
+    match (base, overrides) {
+        (Value::Object(base), Value::Object(overrides)) => {
+            for (key, value) in overrides {
+                merge(base.entry(key).or_insert(Value::Null), value);
+            }
+        }
+        (base, overrides) => *base = overrides,
+    }
+}
+
+/// Neuron `id` of `layer` sending to `forward`, with the settings sections
+/// of the `settings` object; `null` for none
+pub fn neuron_config(id: &str, layer: &str, forward: &[&str], settings: Value) -> NeuronConfig {
+    let settings = match settings {
+        Value::Object(sections) => sections.into_iter().collect(),
+        Value::Null => Default::default(),
+        other => panic!("settings must be an object: {}", other),
+    };
+    NeuronConfig {
+        id: id.to_string(),
+        layer: layer.to_string(),
+        claude_command: "claude".to_string(),
+        system_prompt: None,
+        forward_connections: forward.iter().map(|s| s.to_string()).collect(),
+        backward_connections: vec![],
+        settings,
+    }
+}
+
+/// Answers `response` after `delay`, streamed a line at a time, keeping
+/// the prompts it was sent; fails if `down` is set by the time it answers
+pub struct ScriptedProvider {
+    pub response: &'static str,
+    pub prompts: Arc<Mutex<Vec<String>>>,
+    pub delay: Duration,
+    pub down: Arc<AtomicBool>,
+}
+
+impl ScriptedProvider {
+    pub fn new(response: &'static str) -> Self {
+        Self { response, prompts: Default::default(), delay: Duration::ZERO, down: Default::default() }
+    }
+}
+
+#[async_trait]
+impl ClaudeInterface for ScriptedProvider {
+    async fn send_message(&self, message: &str) -> Result<String> {
+        tokio::time::sleep(self.delay).await;
+        if self.down.load(Ordering::SeqCst) {
+            return Err(Error::Network("provider unreachable".to_string()));
+        }
+        self.prompts.lock().unwrap().push(message.to_string());
+        Ok(self.response.to_string())
+    }
+
+    async fn stream_message(&self, message: &str, partial: &mpsc::UnboundedSender<String>) -> Result<String> {
+        let response = self.send_message(message).await?;
+        for line in response.split_inclusive('\n') {
+            let _ = partial.send(line.to_string());
+        }
+        Ok(response)
+    }
+
+    fn system_prompt(&self) -> &str {
+        ""
+    }
+
+    fn last_token_usage(&self) -> Option<TokenUsage> {
+        None
+    }
+}
+
+/// A response read in full
+pub struct Reply {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: Vec<u8>,
+}
+
+impl Reply {
+    /// The body as JSON, `null` when it is not
+    pub fn json(&self) -> Value {
+        serde_json::from_slice(&self.body).unwrap_or(Value::Null)
+    }
+
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.body).into_owned()
+    }
+
+    pub fn header(&self, name: HeaderName) -> Option<&str> {
+        self.headers.get(name).and_then(|value| value.to_str().ok())
+    }
+}
+
+/// `method uri` with `headers`, carrying `body` as JSON when given
+pub fn request(method: &str, uri: &str, headers: &[(&str, &str)], body: Option<Value>) -> Request<Body> {
+    let mut request = Request::builder().method(method).uri(uri);
+    for (name, value) in headers {
+        request = request.header(*name, *value);
+    }
+    let body = match body {
+        Some(body) => {
+            request = request.header(header::CONTENT_TYPE, "application/json");
+            Body::from(body.to_string())
+        }
+        None => Body::empty(),
+    };
+    request.body(body).unwrap()
+}
+
+/// Send `request` through `app` and read the whole response
+pub async fn send(app: &Router, request: Request<Body>) -> Reply {
+    let response = app.clone().oneshot(request).await.unwrap();
+    let (status, headers) = (response.status(), response.headers().clone());
+    let body = response.into_body().collect().await.unwrap().to_bytes().to_vec();
+    Reply { status, headers, body }
+}
+
+/// `method uri` with an optional JSON body, answered with the status and
+/// the body as JSON
+pub async fn call(app: &Router, method: &str, uri: &str, body: Option<Value>) -> (StatusCode, Value) {
+    let reply = send(app, request(method, uri, &[], body)).await;
+    (reply.status, reply.json())
+}
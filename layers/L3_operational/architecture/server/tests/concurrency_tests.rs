@@ -0,0 +1,242 @@
+//! Tests for per-neuron concurrency limits and overflow handling
+
+mod common;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde_json::json;
+use uuid::Uuid;
+
+use hal9_core::{config::ClaudeConfig, Layer, NeuronConfig, NeuronInterface, NeuronSignal};
+use hal9_server::{
+    claude::MockClaude,
+    concurrency::{Admission, ConcurrencyConfig, ConcurrencyLimiter, OverflowMode},
+    metrics::Metrics,
+    neuron::{ManagedNeuron, NeuronRegistry},
+    router::{RoutingTable, SignalRouter},
+};
+
+use common::neuron_config;
+
+/// An L2 neuron taking `delay_ms` per signal
+fn slow_neuron(config: NeuronConfig, delay_ms: u64) -> ManagedNeuron {
+    let mut claude = MockClaude::new("L2", &ClaudeConfig::default());
+    claude.set_delay(delay_ms);
+    ManagedNeuron::new(config, Box::new(claude)).unwrap()
+}
+
+async fn start_router(registry: Arc<NeuronRegistry>, configs: &[NeuronConfig]) -> SignalRouter {
+    let routing_table = Arc::new(RoutingTable::new());
+    routing_table.build_from_configs(configs);
+    let mut router = SignalRouter::new(registry, routing_table);
+    router.start().await.unwrap();
+    router
+}
+
+async fn processed(registry: &NeuronRegistry, id: &str) -> u64 {
+    registry.get(id).unwrap().health().await.unwrap().signals_processed
+}
+
+#[test]
+fn test_defaults_give_lower_layers_more_concurrency() {
+    let l1 = ConcurrencyConfig::for_layer(Layer::L1);
+    let l2 = ConcurrencyConfig::for_layer(Layer::L2);
+    let l4 = ConcurrencyConfig::for_layer(Layer::L4);
+    let l9 = ConcurrencyConfig::for_layer(Layer::L9);
+
+    assert!(l1.max_concurrent > l2.max_concurrent);
+    assert!(l2.max_concurrent > l4.max_concurrent);
+    assert!(l4.max_concurrent > l9.max_concurrent);
+    assert!(l9.max_concurrent >= 1);
+    assert_eq!(l2.overflow, OverflowMode::Reject);
+}
+
+#[test]
+fn test_settings_override_layer_defaults() {
+    let config = neuron_config("impl", "L2", &[], json!({"concurrency": {"max_queued": 3, "overflow": "shed", "fallback": "spare"}}));
+    let limits = ConcurrencyConfig::for_neuron(&config, Layer::L2).unwrap();
+    assert_eq!(limits.max_concurrent, ConcurrencyConfig::for_layer(Layer::L2).max_concurrent);
+    assert_eq!(limits.max_queued, 3);
+    assert_eq!(limits.overflow, OverflowMode::Shed);
+    assert_eq!(limits.fallback.as_deref(), Some("spare"));
+
+    for bad in [
+        json!({"overflow": "shed"}),
+        json!({"overflow": "shed", "fallback": "impl"}),
+        json!({"max_concurrent": 0}),
+        json!({"overflow": "drop"}),
+        json!({"max_inflight": 2}),
+    ] {
+        let config = neuron_config("impl", "L2", &[], json!({"concurrency": bad.clone()}));
+        assert!(ManagedNeuron::new(config, Box::new(MockClaude::scripted("L2", vec![]))).is_err(), "{}", bad);
+    }
+}
+
+#[tokio::test]
+async fn test_slow_neuron_queue_stays_bounded() {
+    let metrics = Arc::new(Metrics::new());
+    let mut registry = NeuronRegistry::new();
+    registry.set_metrics(metrics.clone());
+    let registry = Arc::new(registry);
+
+    let config = neuron_config("impl", "L2", &[], json!({"concurrency": {"max_concurrent": 2, "max_queued": 8}}));
+    registry.register(slow_neuron(config.clone(), 100)).await.unwrap();
+    let router = start_router(registry.clone(), &[config]).await;
+
+    for i in 0..300 {
+        router.send_signal(NeuronSignal::forward("client", "impl", "L4", "L2", format!("task {}", i))).await.unwrap();
+    }
+
+    let neuron = registry.get("impl").unwrap();
+    for _ in 0..40 {
+        let stats = neuron.concurrency().stats();
+        assert!(stats.in_flight <= 2, "{:?}", stats);
+        assert!(stats.queued <= 8, "{:?}", stats);
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+
+    let stats = neuron.concurrency().stats();
+    assert!(stats.rejected >= 250, "{:?}", stats);
+    assert_eq!(registry.dead_letters().len() as u64, stats.rejected);
+    assert!(registry.dead_letters().list().iter().all(|letter| letter.neuron_id == "impl"));
+    assert_eq!(metrics.snapshot().neuron_queues["impl"].rejected, stats.rejected);
+    assert_eq!(registry.get_info("impl").await.unwrap().concurrency.rejected, stats.rejected);
+}
+
+#[tokio::test]
+async fn test_dead_letters_are_capped() {
+    let registry = Arc::new(NeuronRegistry::new());
+    let config = neuron_config("impl", "L2", &[], json!({"concurrency": {"max_concurrent": 1, "max_queued": 0}}));
+    registry.register(slow_neuron(config.clone(), 500)).await.unwrap();
+    let router = start_router(registry.clone(), &[config]).await;
+
+    for i in 0..1_200 {
+        router.send_signal(NeuronSignal::forward("client", "impl", "L4", "L2", format!("task {}", i))).await.unwrap();
+    }
+    tokio::time::sleep(Duration::from_millis(300)).await;
+
+    assert!(registry.get("impl").unwrap().concurrency().stats().rejected > 1_000);
+    assert_eq!(registry.dead_letters().len(), hal9_server::concurrency::DEAD_LETTER_CAPACITY);
+}
+
+#[tokio::test]
+async fn test_shed_signals_reach_fallback() {
+    let registry = Arc::new(NeuronRegistry::new());
+    let primary = neuron_config("impl", "L2", &[], json!({"concurrency": {
+        "max_concurrent": 1, "max_queued": 0, "overflow": "shed", "fallback": "spare"
+    }}));
+    let spare = neuron_config("spare", "L2", &[], json!({"concurrency": {"max_concurrent": 16, "max_queued": 64}}));
+    registry.register(slow_neuron(primary.clone(), 100)).await.unwrap();
+    registry.register(slow_neuron(spare.clone(), 100)).await.unwrap();
+    let router = start_router(registry.clone(), &[primary, spare]).await;
+
+    for i in 0..10 {
+        router.send_signal(NeuronSignal::forward("client", "impl", "L4", "L2", format!("task {}", i))).await.unwrap();
+    }
+    tokio::time::sleep(Duration::from_millis(600)).await;
+
+    let shed = registry.get("impl").unwrap().concurrency().stats().shed;
+    assert!(shed >= 8, "shed {}", shed);
+    assert_eq!(processed(&registry, "spare").await, shed);
+    assert!(registry.dead_letters().is_empty());
+}
+
+/// L3 signals processed within a window while L2 takes 100ms per signal
+async fn l3_throughput(overflow: &str) -> (u64, u64) {
+    let registry = Arc::new(NeuronRegistry::new());
+    let script = (0..40)
+        .map(|i| format!("FORWARD_TO: impl\nCONTENT:\nbuild part {}", i))
+        .collect();
+    let design = neuron_config("design", "L3", &["impl"], json!({"concurrency": {"max_concurrent": 1, "max_queued": 100}}));
+    let implementation = neuron_config("impl", "L2", &[], json!({"concurrency": {
+        "max_concurrent": 1, "max_queued": 1, "overflow": overflow
+    }}));
+    registry
+        .register(ManagedNeuron::new(design.clone(), Box::new(MockClaude::scripted("L3", script))).unwrap())
+        .await
+        .unwrap();
+    registry.register(slow_neuron(implementation.clone(), 100)).await.unwrap();
+    let router = start_router(registry.clone(), &[design, implementation]).await;
+
+    for i in 0..40 {
+        router.send_signal(NeuronSignal::forward("client", "design", "L4", "L3", format!("feature {}", i))).await.unwrap();
+    }
+    tokio::time::sleep(Duration::from_millis(700)).await;
+
+    let rejected = registry.get("impl").unwrap().concurrency().stats().rejected;
+    (processed(&registry, "design").await, rejected)
+}
+
+#[tokio::test]
+async fn test_block_mode_slows_upstream_emission() {
+    let (unblocked, rejected) = l3_throughput("reject").await;
+    assert_eq!(unblocked, 40);
+    assert!(rejected > 0);
+
+    let (blocked, rejected) = l3_throughput("block").await;
+    assert_eq!(rejected, 0);
+    // L2 drains about one signal per 100ms, so L3 is held to a similar pace
+    assert!(blocked < 20, "L3 processed {} signals against a blocking L2", blocked);
+    assert!(blocked > 0);
+}
+
+#[tokio::test]
+async fn test_fan_out_beyond_a_blocking_queue_completes() {
+    let registry = Arc::new(NeuronRegistry::new());
+    let design = neuron_config("design", "L3", &["impl"], serde_json::Value::Null);
+    // On L1, which caches no responses, so every copy is processed
+    let implementation = neuron_config("impl", "L1", &[], json!({"concurrency": {
+        "max_concurrent": 1, "max_queued": 1, "overflow": "block"
+    }}));
+    let script = vec!["FORWARD_TO: impl, impl, impl, impl, impl\nCONTENT:\nbuild every part".to_string()];
+    registry
+        .register(ManagedNeuron::new(design.clone(), Box::new(MockClaude::scripted("L3", script))).unwrap())
+        .await
+        .unwrap();
+    registry.register(slow_neuron(implementation.clone(), 20)).await.unwrap();
+    let router = start_router(registry.clone(), &[design, implementation]).await;
+
+    // Five signals for room of two are sent as room frees up
+    router.send_signal(NeuronSignal::forward("client", "design", "L4", "L3", "feature".to_string())).await.unwrap();
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while processed(&registry, "impl").await < 5 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("fan-out to a blocking neuron never finished");
+
+    let stats = registry.get("impl").unwrap().concurrency().stats();
+    assert_eq!((stats.in_flight, stats.queued, stats.rejected), (0, 0, 0));
+}
+
+#[tokio::test]
+async fn test_room_for_signals_never_admitted_is_given_back() {
+    let limiter = ConcurrencyLimiter::new(ConcurrencyConfig {
+        max_concurrent: 1,
+        max_queued: 0,
+        overflow: OverflowMode::Block,
+        fallback: None,
+    });
+    let (lost, sent) = (Uuid::new_v4(), Uuid::new_v4());
+
+    // Room kept for a signal whose send failed holds the queue until released
+    limiter.reserve_room(lost).await;
+    assert_eq!(limiter.stats().queued, 0);
+    assert!(tokio::time::timeout(Duration::from_millis(50), limiter.reserve_room(sent)).await.is_err());
+    limiter.release_room(&lost);
+    tokio::time::timeout(Duration::from_millis(50), limiter.reserve_room(sent)).await.unwrap();
+
+    // A signal admitted with its room takes no more, one dropped before
+    // admission gives it back
+    let Admission::Admitted(permit) = limiter.admit_reserved(limiter.take_reservation(&sent)).await else {
+        panic!("reserved signal not admitted");
+    };
+    assert_eq!(limiter.stats().in_flight, 1);
+    drop(permit);
+    limiter.reserve_room(sent).await;
+    drop(limiter.take_reservation(&sent));
+    assert!(limiter.take_reservation(&sent).is_none());
+    assert!(matches!(limiter.admit().await, Admission::Admitted(_)));
+}
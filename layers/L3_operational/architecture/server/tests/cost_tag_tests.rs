@@ -0,0 +1,240 @@
+//! Cost attribution tags: propagation, aggregation and cardinality limits
+
+mod common;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, TimeZone, Utc};
+use serde_json::json;
+
+use hal9_core::config::{BudgetPeriod, CostControls, CostTagConfig};
+use hal9_core::NeuronSignal;
+use hal9_server::chain_tracker::{ChainStatus, ChainTracker, PARENT_ID_KEY};
+use hal9_server::cost_tags::{chain_spend, decode, to_csv, CostTags, TagLedger, TagSpend, TAGS_KEY};
+use hal9_server::cost_tracker::CostTracker;
+use hal9_server::error::ServerError;
+use hal9_server::server::HAL9Server;
+
+fn tags(pairs: &[(&str, &str)]) -> CostTags {
+    pairs.iter().map(|(key, value)| (key.to_string(), value.to_string())).collect()
+}
+
+fn tag_config(max_values_per_key: usize) -> CostTagConfig {
+    CostTagConfig {
+        allowed_keys: vec!["project".to_string(), "team".to_string()],
+        max_values_per_key,
+    }
+}
+
+fn day(month: u32, day: u32) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2026, month, day, 10, 0, 0).unwrap()
+}
+
+fn spend(cost_usd: f64) -> TagSpend {
+    TagSpend { chains: 1, signals: 2, prompt_tokens: 100, completion_tokens: 50, cost_usd }
+}
+
+fn tagged_signal(neuron: &str, layer: &str, encoded: &str) -> NeuronSignal {
+    let mut signal = NeuronSignal::forward("api-client", neuron, "API", layer, "Plan the checkout".into());
+    signal.metadata.insert(TAGS_KEY.to_string(), encoded.to_string());
+    signal
+}
+
+#[tokio::test]
+async fn test_tags_propagate_through_multi_layer_chain() {
+    let config = common::mock_config(json!({
+        "server_id": "cost-tags-test",
+        "neurons": [
+            {"id": "planner", "layer": "L4", "forward_connections": ["designer"], "backward_connections": []},
+            {"id": "designer", "layer": "L3", "forward_connections": ["coder"], "backward_connections": ["planner"]},
+            {"id": "coder", "layer": "L2", "forward_connections": [], "backward_connections": ["designer"]},
+        ],
+        "claude": {
+            "mock_responses": {
+                "L4": [{"trigger": "default", "response": "FORWARD_TO: designer\nCONTENT: Design the checkout", "delay_ms": 0}],
+                "L3": [{"trigger": "default", "response": "FORWARD_TO: coder\nCONTENT: Build the cart", "delay_ms": 0}],
+                "L2": [{"trigger": "default", "response": "RESULT: Cart built", "delay_ms": 0}],
+            },
+            "cost_controls": {"tags": {"allowed_keys": ["project", "team"], "max_values_per_key": 1}},
+        },
+    }));
+    let server = Arc::new(HAL9Server::new(config));
+    server.start().await.unwrap();
+
+    // Tags given out of order are stored sorted
+    let chain_id = server.submit_signal_as(None, tagged_signal("planner", "L4", "team=growth,project=checkout")).await.unwrap();
+    let result = tokio::time::timeout(Duration::from_secs(10), async {
+        loop {
+            let result = server.get_chain_result(&chain_id).await.unwrap();
+            if result.status != ChainStatus::Running {
+                return result;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("chain did not finish");
+
+    let expected = tags(&[("project", "checkout"), ("team", "growth")]);
+    assert_eq!(result.layers, ["L4", "L3", "L2"]);
+    assert_eq!(result.tags, expected);
+    assert_eq!(serde_json::to_value(&result).unwrap()["tags"], json!({"project": "checkout", "team": "growth"}));
+
+    // Every step carried the root's tags
+    let record = server.chain_tracker().get(&chain_id).unwrap();
+    assert_eq!(record.steps.len(), 3);
+    assert!(record.steps.iter().all(|step| step.tags == expected), "{:?}", record.steps);
+
+    // And all of the chain's spend lands on them once recorded
+    let report = tokio::time::timeout(Duration::from_secs(10), async {
+        loop {
+            let report = server.cost_by_tag("project", BudgetPeriod::Monthly).unwrap();
+            if report.total.chains > 0 {
+                return report;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("chain spend not recorded");
+    assert_eq!(report.groups.len(), 1);
+    assert_eq!(report.groups[0].value.as_deref(), Some("checkout"));
+    assert_eq!(report.groups[0].combinations[0].tags, expected);
+    assert_eq!((report.total.chains, report.total.signals), (1, 3));
+    assert_eq!(report.total.prompt_tokens, result.prompt_tokens);
+    assert!(report.total.cost_usd > 0.0);
+
+    // A second project is past the limit of one value per key; the first
+    // is still accepted
+    let err = server.submit_signal_as(None, tagged_signal("planner", "L4", "project=search")).await.unwrap_err();
+    assert!(matches!(&err, ServerError::InvalidInput(msg) if msg.contains("already has 1 distinct values")), "{}", err);
+    assert!(server.submit_signal_as(None, tagged_signal("planner", "L4", "project=checkout")).await.is_ok());
+
+    for bad in ["region=eu", "project", "project=check out"] {
+        let err = server.submit_signal_as(None, tagged_signal("planner", "L4", bad)).await.unwrap_err();
+        assert!(matches!(err, ServerError::InvalidInput(_)), "{}: {}", bad, err);
+    }
+    assert!(matches!(server.cost_by_tag("region", BudgetPeriod::Monthly), Err(ServerError::InvalidInput(_))));
+
+    server.shutdown().await.unwrap();
+}
+
+#[test]
+fn test_chain_spend_follows_step_tags() {
+    let tracker = ChainTracker::new();
+    let mut root = tagged_signal("planner", "L4", "project=checkout");
+    let chain_id = tracker.start(&mut root);
+    tracker.record_usage(&root, 1000, 500);
+    tracker.record_step(&root, Ok("FORWARD_TO: designer"), 2);
+
+    let mut inherited = NeuronSignal::forward("planner", "designer", "L4", "L3", "design".into());
+    inherited.metadata = root.metadata.clone();
+    inherited.metadata.insert(PARENT_ID_KEY.to_string(), root.signal_id.to_string());
+    tracker.record_usage(&inherited, 2000, 1000);
+    tracker.record_model(&inherited, "claude-3-haiku-20240307");
+    tracker.record_step(&inherited, Ok("done"), 0);
+
+    // A signal that lost its tags is charged to no tags
+    let mut stripped = inherited.clone();
+    stripped.signal_id = uuid::Uuid::new_v4();
+    stripped.metadata.remove(TAGS_KEY);
+    tracker.record_usage(&stripped, 400, 0);
+    tracker.record_step(&stripped, Ok("done"), 0);
+
+    let record = tracker.get(&chain_id).unwrap();
+    assert_eq!(record.status, ChainStatus::Completed);
+    let spend = chain_spend(&record, "claude-3-sonnet-20240229");
+    assert_eq!(spend.len(), 2);
+
+    let (untagged, untagged_spend) = &spend[0];
+    assert!(untagged.is_empty());
+    assert_eq!((untagged_spend.chains, untagged_spend.signals, untagged_spend.prompt_tokens), (0, 1, 400));
+    // Sonnet at $0.003 per 1k prompt tokens
+    assert!((untagged_spend.cost_usd - 0.0012).abs() < 1e-12);
+
+    let (checkout, checkout_spend) = &spend[1];
+    assert_eq!(checkout, &tags(&[("project", "checkout")]));
+    assert_eq!((checkout_spend.chains, checkout_spend.signals), (1, 2));
+    assert_eq!((checkout_spend.prompt_tokens, checkout_spend.completion_tokens), (3000, 1500));
+    // Sonnet root: 1.0 * 0.003 + 0.5 * 0.015; haiku child: 2.0 * 0.00025 + 1.0 * 0.00125
+    assert!((checkout_spend.cost_usd - (0.0105 + 0.00175)).abs() < 1e-12, "{}", checkout_spend.cost_usd);
+}
+
+#[test]
+fn test_spend_aggregates_by_tag_value() {
+    let mut ledger = TagLedger::default();
+    ledger.record(&tags(&[("project", "checkout"), ("team", "growth")]), spend(0.5), day(10, 3));
+    ledger.record(&tags(&[("project", "checkout"), ("team", "core")]), spend(0.25), day(10, 10));
+    ledger.record(&tags(&[("project", "search")]), spend(1.0), day(10, 12));
+    ledger.record(&tags(&[("team", "growth")]), spend(0.0625), day(10, 14));
+    ledger.record(&tags(&[]), spend(0.125), day(10, 12));
+    // Last month, outside the report
+    ledger.record(&tags(&[("project", "checkout"), ("team", "growth")]), spend(9.0), day(9, 30));
+    // Same combination on another day adds up
+    ledger.record(&tags(&[("project", "checkout"), ("team", "growth")]), spend(0.5), day(10, 14));
+
+    let now = day(10, 15);
+    let report = ledger.report("project", BudgetPeriod::Monthly, now);
+    assert_eq!(report.period_start, Utc.with_ymd_and_hms(2026, 10, 1, 0, 0, 0).unwrap());
+    assert_eq!(report.period_end, Utc.with_ymd_and_hms(2026, 11, 1, 0, 0, 0).unwrap());
+    assert_eq!(report.total.chains, 6);
+    assert_eq!(report.total.cost_usd, 2.4375);
+
+    let values: Vec<_> = report.groups.iter().map(|g| (g.value.as_deref(), g.spend.cost_usd)).collect();
+    assert_eq!(values, vec![(Some("checkout"), 1.25), (Some("search"), 1.0), (None, 0.1875)]);
+    let checkout = &report.groups[0];
+    assert_eq!(checkout.spend.chains, 3);
+    assert_eq!(checkout.combinations[0].tags, tags(&[("project", "checkout"), ("team", "growth")]));
+    assert_eq!((checkout.combinations[0].spend.chains, checkout.combinations[0].spend.cost_usd), (2, 1.0));
+    assert_eq!(checkout.combinations[1].spend.cost_usd, 0.25);
+    assert_eq!(report.groups[2].combinations.len(), 2);
+
+    // Weeks start on Monday the 12th
+    let report = ledger.report("team", BudgetPeriod::Weekly, now);
+    let values: Vec<_> = report.groups.iter().map(|g| (g.value.as_deref(), g.spend.cost_usd)).collect();
+    assert_eq!(values, vec![(Some("growth"), 0.5625), (None, 1.125)]);
+
+    let csv = to_csv(&ledger.report("project", BudgetPeriod::Monthly, now));
+    let lines: Vec<_> = csv.lines().collect();
+    assert_eq!(lines[0], "value,tags,chains,signals,prompt_tokens,completion_tokens,cost_usd");
+    assert_eq!(lines[1], "checkout,\"project=checkout,team=growth\",2,4,200,100,1");
+    assert_eq!(lines[3], "search,project=search,1,2,100,50,1");
+    assert_eq!(lines.len(), 1 + 5);
+    assert!(lines[5..].iter().all(|line| !line.starts_with("checkout") && !line.starts_with("search")));
+}
+
+#[test]
+fn test_cardinality_limit_is_enforced() {
+    let tracker = CostTracker::new(CostControls { tags: tag_config(2), ..Default::default() });
+
+    assert!(tracker.admit_tags(&tags(&[("project", "checkout"), ("team", "growth")])).is_ok());
+    assert!(tracker.admit_tags(&tags(&[("project", "search")])).is_ok());
+    // Known values are always accepted
+    assert!(tracker.admit_tags(&tags(&[("project", "checkout"), ("team", "growth")])).is_ok());
+
+    let err = tracker.admit_tags(&tags(&[("project", "billing")])).unwrap_err();
+    assert!(err.to_string().contains("Cost tag 'project' already has 2 distinct values; 'billing' refused"), "{}", err);
+
+    // A refused set registers none of its values
+    assert!(tracker.admit_tags(&tags(&[("project", "billing"), ("team", "core")])).is_err());
+    let mut ledger = TagLedger::default();
+    assert!(ledger.admit(&tags(&[("project", "checkout"), ("team", "core")]), &tag_config(2)).is_ok());
+    assert!(ledger.admit(&tags(&[("team", "growth")]), &tag_config(2)).is_ok());
+    // The project would fit, the team does not
+    assert!(ledger.admit(&tags(&[("project", "search"), ("team", "infra")]), &tag_config(2)).is_err());
+    assert_eq!((ledger.value_count("project"), ledger.value_count("team")), (1, 2));
+
+    // Only allowed keys
+    let err = tracker.admit_tags(&tags(&[("region", "eu")])).unwrap_err();
+    assert!(err.to_string().contains("not allowed"), "{}", err);
+    assert!(CostTracker::new(CostControls::default()).admit_tags(&tags(&[("project", "checkout")])).is_err());
+
+    // Encoded tags must be well formed
+    assert_eq!(decode("project=checkout,team=growth").unwrap(), tags(&[("project", "checkout"), ("team", "growth")]));
+    assert!(decode("").unwrap().is_empty());
+    let too_long = format!("project={}", "x".repeat(65));
+    for bad in ["project", "project=", "project=a,project=b", "project=check out", too_long.as_str()] {
+        assert!(decode(bad).is_err(), "{}", bad);
+    }
+}
@@ -1,7 +1,7 @@
 //! Cost tracker unit tests
 
 use hal9_server::cost_tracker::CostTracker;
-use hal9_core::config::CostControls;
+use hal9_core::config::{BudgetPeriod, BudgetPeriodConfig, CostControls};
 use std::sync::Arc;
 use tokio::sync::Mutex;
 
@@ -12,6 +12,8 @@ async fn test_cost_limits() {
         max_cost_per_day: 10.0,
         max_tokens_per_request: 1000,
         alert_threshold: 0.8,
+        budget_period: None,
+        tags: Default::default(),
     };
     
     let tracker = CostTracker::new(config);
@@ -47,6 +49,8 @@ async fn test_token_limits() {
         max_cost_per_day: 1000.0,
         max_tokens_per_request: 500,
         alert_threshold: 0.8,
+        budget_period: None,
+        tags: Default::default(),
     };
     
     let tracker = CostTracker::new(config);
@@ -67,6 +71,8 @@ async fn test_alert_callback() {
         max_cost_per_day: 10.0,
         max_tokens_per_request: 1000,
         alert_threshold: 0.8,
+        budget_period: None,
+        tags: Default::default(),
     };
     
     let mut tracker = CostTracker::new(config);
@@ -103,6 +109,8 @@ async fn test_daily_limits() {
         max_cost_per_day: 1.0,    // Low daily limit
         max_tokens_per_request: 1000,
         alert_threshold: 0.8,
+        budget_period: None,
+        tags: Default::default(),
     };
     
     let tracker = CostTracker::new(config);
@@ -129,6 +137,8 @@ async fn test_cost_stats() {
         max_cost_per_day: 100.0,
         max_tokens_per_request: 1000,
         alert_threshold: 0.8,
+        budget_period: None,
+        tags: Default::default(),
     };
     
     let tracker = CostTracker::new(config);
@@ -162,4 +172,65 @@ async fn test_window_reset() {
     
     let stats = tracker.get_stats().await;
     assert!(stats.hourly_cost > 0.0);
-}
\ No newline at end of file
+}
+fn period_controls(dir: &tempfile::TempDir, fallback_model: Option<&str>) -> CostControls {
+    CostControls {
+        max_cost_per_hour: 100.0,
+        max_cost_per_day: 1000.0,
+        max_tokens_per_request: 1000,
+        alert_threshold: 0.8,
+        budget_period: Some(BudgetPeriodConfig {
+            period: BudgetPeriod::Monthly,
+            budget: 1.0,
+            timezone: "+09:00".to_string(),
+            max_carryover: 0.0,
+            fallback_model: fallback_model.map(str::to_string),
+            state_path: dir.path().join("budget.json").to_string_lossy().into_owned(),
+        }),
+        tags: Default::default(),
+    }
+}
+
+#[tokio::test]
+async fn test_period_budget_hard_limit() {
+    let dir = tempfile::tempdir().unwrap();
+    let tracker = CostTracker::new(period_controls(&dir, None));
+    
+    tracker.record_cost(1.2, 1000).await;
+    
+    assert!(tracker.check_request(100).await.is_err());
+    assert_eq!(tracker.downgrade_model().await, None);
+}
+
+#[tokio::test]
+async fn test_period_budget_downgrades_to_fallback() {
+    let dir = tempfile::tempdir().unwrap();
+    let tracker = CostTracker::new(period_controls(&dir, Some("claude-3-haiku-20240307")));
+    
+    // Within budget the configured model is kept
+    tracker.record_cost(0.5, 500).await;
+    assert_eq!(tracker.downgrade_model().await, None);
+    
+    // Once exhausted, requests continue on the fallback model
+    tracker.record_cost(0.6, 600).await;
+    assert!(tracker.check_request(100).await.is_ok());
+    assert_eq!(tracker.downgrade_model().await.as_deref(), Some("claude-3-haiku-20240307"));
+    
+    let status = tracker.get_stats().await.period.unwrap();
+    assert_eq!(status.remaining, 0.0);
+    assert_eq!(status.downgraded_to.as_deref(), Some("claude-3-haiku-20240307"));
+}
+
+#[tokio::test]
+async fn test_period_spend_survives_restart() {
+    let dir = tempfile::tempdir().unwrap();
+    
+    let tracker = CostTracker::new(period_controls(&dir, None));
+    tracker.record_cost(0.4, 400).await;
+    drop(tracker);
+    
+    let restarted = CostTracker::new(period_controls(&dir, None));
+    let status = restarted.period_status().await.unwrap();
+    assert!((status.consumed - 0.4).abs() < 1e-9);
+    assert!(status.resets_at > status.period_start);
+}
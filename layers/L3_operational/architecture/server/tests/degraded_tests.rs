@@ -0,0 +1,183 @@
+//! Degraded mode: template decomposition and parked branches while every
+//! provider is down, and their resumption once one recovers
+
+mod common;
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde_json::json;
+use tokio::sync::mpsc;
+
+use hal9_core::config::DegradedConfig;
+use hal9_core::NeuronSignal;
+use hal9_server::chain_tracker::{ChainStatus, ChainTracker};
+use hal9_server::circuit_breaker::{CircuitBreakerConfig, CircuitState};
+use hal9_server::degraded::{DegradedAdmission, DegradedMode, DegradedTrigger, DEFERRED_REASON, HEURISTIC_MODEL};
+use hal9_server::error::ServerError;
+use hal9_server::neuron::{ManagedNeuron, NeuronRegistry};
+use hal9_server::router::{RoutingTable, SignalRouter};
+
+use common::{neuron_config, ScriptedProvider};
+
+fn config() -> DegradedConfig {
+    DegradedConfig {
+        templates: vec!["Design {task}".to_string(), "Build {task}".to_string()],
+        max_parked: 3,
+        ..Default::default()
+    }
+}
+
+fn signal(layer: &str, content: &str) -> NeuronSignal {
+    NeuronSignal::forward("planner", "coder", "L4", layer, content.to_string())
+}
+
+#[tokio::test]
+async fn test_forced_mode_parks_and_releases_in_order() {
+    let degraded = DegradedMode::new(config());
+    let (tx, mut rx) = mpsc::channel(10);
+    degraded.attach(tx);
+    assert_eq!(degraded.admit(&signal("L2", "code"), "L2"), DegradedAdmission::Process);
+    assert!(matches!(degraded.force_exit(None).await, Err(ServerError::InvalidInput(_))));
+
+    let status = degraded.force_enter(Some("ops"));
+    assert!(status.active);
+    assert_eq!(status.trigger, Some(DegradedTrigger::Forced));
+    assert_eq!(status.forced_by.as_deref(), Some("ops"));
+
+    assert_eq!(degraded.admit(&signal("L3", "design"), "L4"), DegradedAdmission::Decompose);
+    assert_eq!(degraded.admit(&signal("L2", "code"), "L2"), DegradedAdmission::Park);
+    let gradient = hal9_core::Gradient::new("timeout".to_string(), 0.5);
+    let feedback = NeuronSignal::backward("coder", "planner", "L2", "L4", gradient);
+    assert_eq!(degraded.admit(&feedback, "L4"), DegradedAdmission::Process);
+    assert_eq!(degraded.tasks("the cart"), vec!["Design the cart", "Build the cart"]);
+
+    for content in ["first", "second", "third"] {
+        degraded.park(signal("L2", content), false).unwrap();
+    }
+    let spilled = degraded.park(signal("L2", "fourth"), false).unwrap_err();
+    assert_eq!(spilled.payload.activation.content, "fourth");
+    assert_eq!((degraded.status().parked, degraded.status().spilled), (3, 1));
+
+    // A forced mode outlasts recovered providers
+    let registry = NeuronRegistry::new();
+    assert_eq!(degraded.check_recovery(&registry).await, 0);
+    assert!(degraded.is_active());
+
+    assert_eq!(degraded.force_exit(Some("ops")).await.unwrap(), 3);
+    assert!(!degraded.is_active());
+    for expected in ["first", "second", "third"] {
+        assert_eq!(rx.recv().await.unwrap().payload.activation.content, expected);
+    }
+    assert!(rx.try_recv().is_err());
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_outage_mid_chain_parks_and_resumes_branches() {
+    let down = Arc::new(AtomicBool::new(false));
+    let breaker = CircuitBreakerConfig {
+        failure_threshold: 1,
+        success_threshold: 1,
+        timeout: Duration::from_secs(1),
+        window: Duration::from_secs(60),
+    };
+    let planner_config = neuron_config("planner", "L4", &["coder"], json!({"concurrency": {"max_concurrent": 1}}));
+    let coder_config = neuron_config("coder", "L2", &[], json!({"concurrency": {"max_concurrent": 1}}));
+    let registry = Arc::new(NeuronRegistry::new());
+    for (config, response, delay) in [
+        (&planner_config, "FORWARD_TO: coder\nCONTENT: Implement the cart", Duration::ZERO),
+        (&coder_config, "RESULT: Cart built", Duration::from_secs(1)),
+    ] {
+        let provider = ScriptedProvider { delay, down: down.clone(), ..ScriptedProvider::new(response) };
+        let mut neuron = ManagedNeuron::new(config.clone(), Box::new(provider)).unwrap();
+        neuron.set_circuit_breaker(breaker.clone());
+        registry.register(neuron).await.unwrap();
+    }
+
+    let routing_table = Arc::new(RoutingTable::new());
+    routing_table.build_from_configs(&[planner_config, coder_config]);
+    let chain_tracker = Arc::new(ChainTracker::new());
+    let degraded = Arc::new(DegradedMode::new(config()));
+    let mut router = SignalRouter::new(registry.clone(), routing_table);
+    router.set_chain_tracker(chain_tracker.clone());
+    router.set_degraded(degraded.clone());
+    router.start().await.unwrap();
+
+    let submit = |content: &str| {
+        let mut root = NeuronSignal::forward("api", "planner", "API", "L4", content.to_string());
+        let chain_id = chain_tracker.start(&mut root);
+        (chain_id, root)
+    };
+
+    // The planner answers, then every provider goes down while the coder works
+    let (cart, root) = submit("Build the cart");
+    router.send_signal(root).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    down.store(true, Ordering::SeqCst);
+    let (shop, root) = submit("Build the shop");
+    router.send_signal(root).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert_eq!(chain_tracker.aggregate(&shop).unwrap().status, ChainStatus::Failed);
+    assert!(!degraded.is_active());
+
+    // The coder's failure leaves every breaker open: its branch is parked
+    tokio::time::sleep(Duration::from_secs(1)).await;
+    assert_eq!(registry.get("coder").unwrap().circuit_state().await, CircuitState::Open);
+    assert_eq!(degraded.status().trigger, Some(DegradedTrigger::Outage));
+    let result = chain_tracker.aggregate(&cart).unwrap();
+    assert_eq!(result.status, ChainStatus::Running);
+    assert!(result.errors.is_empty());
+    assert_eq!((result.steps_completed, result.pending_signals), (1, 1));
+    assert_eq!(result.deferred.len(), 1);
+    assert_eq!(result.deferred[0].neuron_id, "coder");
+    assert_eq!(result.deferred[0].reason, DEFERRED_REASON);
+    assert!(result.deferred[0].resumed_at.is_none());
+
+    // The planner splits new tasks with templates; both branches park
+    let (checkout, root) = submit("the checkout");
+    router.send_signal(root).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    let record = chain_tracker.get(&checkout).unwrap();
+    assert_eq!(record.steps[0].model.as_deref(), Some(HEURISTIC_MODEL));
+    assert_eq!(
+        record.steps[0].output.as_deref(),
+        Some("DEGRADED: split into 2 tasks with local templates\n- coder: Design the checkout\n- coder: Build the checkout")
+    );
+    assert_eq!(record.deferred.len(), 2);
+    assert_eq!(degraded.status().parked, 3);
+
+    // A probe while the breakers are still open parks again, first in line
+    degraded.probe(&registry).await;
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert_eq!(degraded.status().parked, 3);
+    assert_eq!(chain_tracker.aggregate(&cart).unwrap().deferred.len(), 1);
+
+    // Once providers recover the probe gets through and releases the rest
+    down.store(false, Ordering::SeqCst);
+    tokio::time::sleep(Duration::from_secs(1)).await;
+    degraded.probe(&registry).await;
+    tokio::time::sleep(Duration::from_secs(5)).await;
+    assert!(!degraded.is_active());
+    assert_eq!(degraded.status().parked, 0);
+
+    let cart = chain_tracker.get(&cart).unwrap();
+    assert_eq!(cart.status, ChainStatus::Completed);
+    assert_eq!(cart.steps.len(), 2);
+    assert_eq!(cart.steps[1].parent_id.as_deref(), Some(cart.steps[0].signal_id.as_str()));
+    assert_eq!(cart.steps[1].output.as_deref(), Some("RESULT: Cart built"));
+    let probed = cart.deferred[0].resumed_at.unwrap();
+    assert!(probed >= cart.deferred[0].deferred_at);
+
+    let checkout = chain_tracker.get(&checkout).unwrap();
+    assert_eq!(checkout.status, ChainStatus::Completed);
+    assert_eq!(checkout.steps.len(), 3);
+    for branch in &checkout.deferred {
+        // Released only after the probe, the first branch parked, resumed
+        let resumed = branch.resumed_at.unwrap();
+        assert!(resumed >= probed);
+        let step = checkout.steps.iter().find(|step| step.signal_id == branch.signal_id).unwrap();
+        assert_eq!(step.output.as_deref(), Some("RESULT: Cart built"));
+    }
+    assert!(checkout.steps[1..].iter().all(|step| step.parent_id.as_deref() == Some(checkout.steps[0].signal_id.as_str())));
+}
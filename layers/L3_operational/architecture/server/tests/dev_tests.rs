@@ -0,0 +1,145 @@
+//! Developer mode: booting the seeded in-memory stack, the demo scenarios'
+//! chains and reloading the topology file
+
+use std::path::Path;
+use std::time::Duration;
+
+use hal9_server::chain_tracker::ChainStatus;
+use hal9_server::dev::{self, DevOptions, DEMO_PASSWORD, ENTRY_NEURON, SCENARIOS};
+
+/// What CI allows for a boot, far above what one takes
+const BOOT_BUDGET: Duration = Duration::from_secs(10);
+
+fn write_topology(path: &Path, reviewer: bool) {
+    let mut topology = String::from(
+        r#"
+neurons:
+  - id: neuron-l4-strategic
+    layer: L4
+    forward_connections: [neuron-l3-design]
+    backward_connections: []
+  - id: neuron-l3-design
+    layer: L3
+    forward_connections: [neuron-l2-impl]
+    backward_connections: [neuron-l4-strategic]
+  - id: neuron-l2-impl
+    layer: L2
+    forward_connections: []
+    backward_connections: [neuron-l3-design]
+"#,
+    );
+    if reviewer {
+        topology = topology.replace("forward_connections: [neuron-l2-impl]", "forward_connections: [neuron-l2-impl, reviewer]");
+        topology.push_str(
+            r#"  - id: reviewer
+    layer: L2
+    system_prompt: Review the design
+    forward_connections: []
+    backward_connections: [neuron-l3-design]
+"#,
+        );
+    }
+    std::fs::write(path, topology).unwrap();
+}
+
+#[tokio::test]
+async fn test_boots_to_ready_with_seeded_users() {
+    let stack = tokio::time::timeout(BOOT_BUDGET, dev::boot(&DevOptions::default()))
+        .await
+        .expect("dev stack did not boot in time")
+        .unwrap();
+    let health = stack.server.health().check().await;
+    assert!(health.is_ready(), "{:?}", health);
+    let ids: Vec<String> = stack.server.topology().into_iter().map(|n| n.id).collect();
+    assert_eq!(ids, ["neuron-l4-strategic", "neuron-l3-design", "neuron-l2-impl"]);
+
+    // Every demo user signs in and has a working key
+    let roles: Vec<(&str, &str)> = stack.users.iter().map(|u| (u.username.as_str(), u.role.as_str())).collect();
+    assert_eq!(roles, [("admin", "admin"), ("developer", "user"), ("guest", "guest")]);
+    let users = stack.server.user_manager.clone().unwrap();
+    let keys = stack.server.api_key_manager.clone().unwrap();
+    for user in &stack.users {
+        assert_eq!(user.password, DEMO_PASSWORD);
+        users.authenticate(&user.username, DEMO_PASSWORD).await.unwrap();
+        keys.validate_api_key(&user.api_key).await.unwrap();
+    }
+
+    // Nothing to run without a scenario
+    assert!(stack.run_scenario(Duration::from_secs(1)).await.is_err());
+    stack.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_scenarios_run_the_chain_down_the_trio() {
+    for (name, input, _) in SCENARIOS {
+        let options = DevOptions { scenario: Some(name.to_string()), ..Default::default() };
+        let stack = dev::boot(&options).await.unwrap();
+        assert_eq!(stack.scenario, Some((*name, *input)));
+
+        let result = stack.run_scenario(Duration::from_secs(10)).await.unwrap();
+        assert_eq!(result.status, ChainStatus::Completed, "{}: {:?}", name, result.errors);
+        assert_eq!(result.input, *input);
+        assert_eq!(result.layers, ["L4", "L3", "L2"], "{}", name);
+        assert_eq!(result.steps_completed, 3, "{}", name);
+        // Answered by the scenario, not the layers' default responses
+        let output = result.final_output.unwrap();
+        assert!(output.starts_with("RESULT: ") && output.contains("implemented"), "{}: {}", name, output);
+
+        // One signal per neuron, each the child of the one before
+        let record = stack.server.chain_record(&result.chain_id).await.unwrap();
+        let neurons: Vec<&str> = record.steps.iter().map(|s| s.neuron_id.as_str()).collect();
+        assert_eq!(neurons, [ENTRY_NEURON, "neuron-l3-design", "neuron-l2-impl"]);
+        for pair in record.steps.windows(2) {
+            assert_eq!(pair[1].parent_id.as_deref(), Some(pair[0].signal_id.as_str()));
+        }
+        stack.shutdown().await.unwrap();
+    }
+
+    let options = DevOptions { scenario: Some("unknown".to_string()), ..Default::default() };
+    let err = dev::boot(&options).await.err().unwrap();
+    assert!(err.to_string().contains("task-manager, e-commerce, chat"), "{}", err);
+}
+
+#[tokio::test]
+async fn test_topology_file_edits_are_applied() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join(dev::TOPOLOGY_FILE);
+    write_topology(&path, false);
+    let options = DevOptions { topology: Some(path.clone()), ..Default::default() };
+    let stack = dev::boot(&options).await.unwrap();
+    assert_eq!(stack.server.topology().len(), 3);
+    let watcher = dev::watch_topology(stack.server.clone(), path.clone(), Duration::from_millis(20));
+
+    // Modification times can be coarse; wait for them to differ
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+    write_topology(&path, true);
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while stack.server.topology().len() != 4 {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+    })
+    .await
+    .expect("topology file was not reloaded");
+    let topology = stack.server.topology();
+    let design = topology.iter().find(|n| n.id == "neuron-l3-design").unwrap();
+    assert_eq!(design.forward_connections, ["neuron-l2-impl", "reviewer"]);
+    let reviewer = topology.iter().find(|n| n.id == "reviewer").unwrap();
+    assert_eq!(reviewer.system_prompt.as_deref(), Some("Review the design"));
+
+    // A file breaking the rules is left unapplied
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+    std::fs::write(&path, "neurons:\n  - id: lonely\n    layer: L99\n    forward_connections: [ghost]\n    backward_connections: []\n").unwrap();
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert_eq!(stack.server.topology().len(), 4);
+    watcher.abort();
+
+    // Reloading with nothing changed is not a patch
+    write_topology(&path, true);
+    assert!(dev::reload_topology(&stack.server, &path).await.unwrap().is_none());
+    write_topology(&path, false);
+    let applied = dev::reload_topology(&stack.server, &path).await.unwrap().unwrap();
+    assert!(applied.diff.iter().any(|d| d.path == "neurons.reviewer" && d.after.is_none()));
+    assert_eq!(stack.server.topology().len(), 3);
+
+    stack.shutdown().await.unwrap();
+}
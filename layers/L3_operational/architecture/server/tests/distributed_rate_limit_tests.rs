@@ -0,0 +1,219 @@
+//! Tests for rate limits shared across server instances
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::process::{Child, Command};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use async_trait::async_trait;
+use axum::{middleware, routing::get, Router};
+use tokio::sync::Mutex;
+
+use hal9_server::metrics::Metrics;
+use hal9_server::rate_limiter::{
+    rate_limit_middleware, CoordinationBackend, CoordinationError, LimitMode, RateLimitConfig, RateLimiter,
+    RedisBackend,
+};
+
+const LIMIT: u32 = 40;
+const MAX_UNSYNCED: u32 = 5;
+
+/// Shared counts held in memory, standing in for Redis
+#[derive(Default)]
+struct MemoryBackend {
+    counts: Mutex<HashMap<(String, u64), u64>>,
+    down: AtomicBool,
+}
+
+#[async_trait]
+impl CoordinationBackend for MemoryBackend {
+    async fn add(&self, key: &str, window: u64, count: u64, _ttl: Duration) -> Result<u64, CoordinationError> {
+        if self.down.load(Ordering::Relaxed) {
+            return Err(CoordinationError("connection refused".to_string()));
+        }
+        let mut counts = self.counts.lock().await;
+        let total = counts.entry((key.to_string(), window)).or_insert(0);
+        *total += count;
+        Ok(*total)
+    }
+}
+
+fn shared_config() -> RateLimitConfig {
+    RateLimitConfig {
+        max_requests: LIMIT,
+        window_duration: Duration::from_secs(60),
+        enabled: true,
+        burst_size: 0,
+        ip_mode: LimitMode::Distributed,
+        user_mode: LimitMode::Local,
+        max_unsynced: MAX_UNSYNCED,
+        sync_interval: Duration::from_millis(50),
+    }
+}
+
+/// Wait until at least `needed` of the current 60s window remains, so a run
+/// is not split across two windows
+async fn wait_for_window(needed: Duration) {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+    let remaining = Duration::from_secs(60 - now.as_secs() % 60);
+    if remaining < needed {
+        tokio::time::sleep(remaining + Duration::from_millis(100)).await;
+    }
+}
+
+#[tokio::test]
+async fn test_instances_share_limit_through_backend() {
+    wait_for_window(Duration::from_secs(10)).await;
+    let backend = Arc::new(MemoryBackend::default());
+    let instances: Vec<_> = (0..2)
+        .map(|_| Arc::new(RateLimiter::with_backend(shared_config(), backend.clone())))
+        .collect();
+
+    let mut tasks = Vec::new();
+    for i in 0..200 {
+        let limiter = instances[i % 2].clone();
+        tasks.push(tokio::spawn(async move { limiter.check_rate_limit("10.0.0.1").await.is_ok() }));
+    }
+    let mut allowed = 0;
+    for task in tasks {
+        allowed += task.await.unwrap() as u32;
+    }
+
+    // Each instance may run up to MAX_UNSYNCED requests ahead of the backend
+    assert!(allowed >= LIMIT, "allowed {}", allowed);
+    assert!(allowed <= LIMIT + 2 * MAX_UNSYNCED, "allowed {}", allowed);
+    assert!(!instances[0].is_degraded());
+}
+
+#[tokio::test]
+async fn test_local_classes_are_not_shared() {
+    let backend = Arc::new(MemoryBackend::default());
+    let first = RateLimiter::with_backend(shared_config(), backend.clone());
+    let second = RateLimiter::with_backend(shared_config(), backend.clone());
+
+    for limiter in [&first, &second] {
+        for _ in 0..LIMIT {
+            assert!(limiter.check_rate_limit("user:alice").await.is_ok());
+        }
+        assert!(limiter.check_rate_limit("user:alice").await.is_err());
+    }
+    assert!(backend.counts.lock().await.is_empty());
+}
+
+#[tokio::test]
+async fn test_backend_outage_degrades_to_local_limits() {
+    wait_for_window(Duration::from_secs(10)).await;
+    let metrics = Arc::new(Metrics::new());
+    let backend = Arc::new(MemoryBackend::default());
+    backend.down.store(true, Ordering::Relaxed);
+    let first = RateLimiter::with_backend(shared_config(), backend.clone());
+    let second = RateLimiter::with_backend(shared_config(), backend.clone());
+    first.set_metrics(metrics.clone());
+
+    // Each instance runs its staleness allowance, then falls back to its own bucket
+    for limiter in [&first, &second] {
+        let mut allowed = 0;
+        for _ in 0..LIMIT * 2 {
+            allowed += limiter.check_rate_limit("10.0.0.2").await.is_ok() as u32;
+        }
+        assert_eq!(allowed, LIMIT + MAX_UNSYNCED);
+    }
+    assert!(first.is_degraded());
+    assert!(metrics.snapshot().rate_limit_degraded);
+
+    // Requests counted during the outage are reported once the backend returns
+    backend.down.store(false, Ordering::Relaxed);
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert!(!first.is_degraded());
+    assert!(!metrics.snapshot().rate_limit_degraded);
+    assert!(first.check_rate_limit("10.0.0.2").await.is_err());
+}
+
+/// One server instance, run as a child process by the multi-instance test
+#[test]
+#[ignore = "spawned by test_two_server_processes_share_redis_limit"]
+fn rate_limit_instance() {
+    let Ok(port) = std::env::var("HAL9_RATE_LIMIT_INSTANCE_PORT") else {
+        return;
+    };
+    let redis_url = std::env::var("REDIS_URL").unwrap();
+    let prefix = std::env::var("HAL9_RATE_LIMIT_PREFIX").unwrap();
+
+    tokio::runtime::Runtime::new().unwrap().block_on(async move {
+        let backend = RedisBackend::with_prefix(&redis_url, &prefix).unwrap();
+        let limiter = Arc::new(RateLimiter::with_backend(shared_config(), Arc::new(backend)));
+        let app = Router::new()
+            .route("/test", get(|| async { "OK" }))
+            .layer(middleware::from_fn(rate_limit_middleware))
+            .layer(axum::Extension(limiter));
+        let listener = tokio::net::TcpListener::bind(("127.0.0.1", port.parse::<u16>().unwrap())).await.unwrap();
+        axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>()).await.unwrap();
+    });
+}
+
+/// Kills the instance processes when the test ends
+struct Instances(Vec<Child>);
+
+impl Drop for Instances {
+    fn drop(&mut self) {
+        for child in &mut self.0 {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+}
+
+fn free_port() -> u16 {
+    std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port()
+}
+
+/// Two server processes sharing one Redis admit at most
+/// LIMIT + 2 * MAX_UNSYNCED requests from a client in one window
+#[tokio::test]
+#[ignore = "needs Redis at REDIS_URL"]
+async fn test_two_server_processes_share_redis_limit() {
+    let redis_url = std::env::var("REDIS_URL").expect("REDIS_URL must point at a test Redis");
+    let prefix = format!("hal9:ratelimit-test:{}", uuid::Uuid::new_v4());
+    let ports = [free_port(), free_port()];
+    let _instances = Instances(
+        ports
+            .iter()
+            .map(|port| {
+                Command::new(std::env::current_exe().unwrap())
+                    .args(["rate_limit_instance", "--exact", "--ignored", "--nocapture"])
+                    .env("REDIS_URL", &redis_url)
+                    .env("HAL9_RATE_LIMIT_PREFIX", &prefix)
+                    .env("HAL9_RATE_LIMIT_INSTANCE_PORT", port.to_string())
+                    .spawn()
+                    .unwrap()
+            })
+            .collect(),
+    );
+
+    for port in ports {
+        let mut attempts = 0;
+        while tokio::net::TcpStream::connect(("127.0.0.1", port)).await.is_err() {
+            attempts += 1;
+            assert!(attempts < 100, "instance on port {} did not start", port);
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+    }
+
+    wait_for_window(Duration::from_secs(20)).await;
+    let client = reqwest::Client::new();
+    let mut requests = Vec::new();
+    for i in 0..300 {
+        let url = format!("http://127.0.0.1:{}/test", ports[i % 2]);
+        let client = client.clone();
+        requests.push(tokio::spawn(async move { client.get(url).send().await.unwrap().status() }));
+    }
+    let mut allowed = 0;
+    for request in requests {
+        allowed += request.await.unwrap().is_success() as u32;
+    }
+
+    assert!(allowed >= LIMIT, "allowed {}", allowed);
+    assert!(allowed <= LIMIT + 2 * MAX_UNSYNCED, "allowed {} across two instances", allowed);
+}
@@ -0,0 +1,166 @@
+//! Fan-out limits: branches over a layer's maximum or the chain's node
+//! budget are dropped at every level, or fail the chain in strict mode
+
+mod common;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde_json::json;
+
+use hal9_core::config::FanOutConfig;
+use hal9_core::{NeuronConfig, NeuronSignal, PropagationType, ServerConfig};
+use hal9_server::chain_tracker::{ChainResult, ChainStatus};
+use hal9_server::fan_out::{self, FanOutPolicy, TruncationReason, FAN_OUT_BUDGET_KEY};
+use hal9_server::server::HAL9Server;
+use hal9_server::{ManagedNeuron, MockClaude};
+
+const BRANCHES: usize = 20;
+
+fn ids(prefix: &str) -> Vec<String> {
+    (0..BRANCHES).map(|i| format!("{}{}", prefix, i)).collect()
+}
+
+/// A planner forwarding to 20 designers, each forwarding to 20 coders
+fn config(strict: bool) -> ServerConfig {
+    let mut neurons = vec![json!({
+        "id": "planner", "layer": "L4", "forward_connections": ids("designer-"), "backward_connections": [],
+    })];
+    for designer in ids("designer-") {
+        neurons.push(json!({
+            "id": designer, "layer": "L3", "forward_connections": ids("coder-"), "backward_connections": ["planner"],
+        }));
+    }
+    for coder in ids("coder-") {
+        neurons.push(json!({"id": coder, "layer": "L2", "forward_connections": [], "backward_connections": []}));
+    }
+    let forward_all = |prefix: &str| format!("FORWARD_TO: {}\nCONTENT: Split it", ids(prefix).join(", "));
+    common::mock_config(json!({
+        "server_id": "fan-out-test",
+        "neurons": neurons,
+        "claude": {
+            "mock_responses": {
+                "L4": [{"trigger": "default", "response": forward_all("designer-"), "delay_ms": 0}],
+                "L3": [{"trigger": "default", "response": forward_all("coder-"), "delay_ms": 0}],
+                "L2": [{"trigger": "default", "response": "RESULT: Built", "delay_ms": 0}],
+            },
+        },
+        "fan_out": {"max_children": {"L4": 5, "L3": 3}, "node_budget": 12, "strict": strict},
+    }))
+}
+
+async fn run_chain(server: &HAL9Server, signal: NeuronSignal) -> ChainResult {
+    let chain_id = server.submit_signal(signal).await.unwrap();
+    tokio::time::timeout(Duration::from_secs(10), async {
+        loop {
+            let result = server.get_chain_result(&chain_id).await.unwrap();
+            if result.status != ChainStatus::Running {
+                return result;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("chain did not finish")
+}
+
+#[tokio::test]
+async fn test_huge_fan_out_is_truncated_at_every_level_within_budget() {
+    let server = Arc::new(HAL9Server::new(config(false)));
+    server.start().await.unwrap();
+
+    // Asking for a larger budget than configured gets the configured one
+    let mut signal = NeuronSignal::forward("api-client", "planner", "API", "L4", "Plan it".into());
+    signal.metadata.insert(FAN_OUT_BUDGET_KEY.to_string(), "1000".to_string());
+    let result = run_chain(&server, signal).await;
+    assert_eq!(result.status, ChainStatus::Completed, "{:?}", result.errors);
+
+    let usage = result.fan_out.expect("fan-out usage");
+    assert_eq!(usage.node_budget, Some(12));
+    assert_eq!(usage.nodes, 12);
+    assert_eq!(result.steps_completed, 12);
+
+    // The planner hits its layer's maximum, every designer the budget it
+    // was handed
+    assert_eq!(usage.truncations.len(), 6);
+    let planner = usage.truncations.iter().find(|t| t.layer == "L4").unwrap();
+    assert_eq!((planner.requested, planner.allowed, planner.reason), (BRANCHES, 5, TruncationReason::MaxChildren));
+    let designers: Vec<_> = usage.truncations.iter().filter(|t| t.layer == "L3").collect();
+    assert_eq!(designers.len(), 5);
+    assert!(designers.iter().all(|t| t.reason == TruncationReason::NodeBudget && !t.failed));
+    assert_eq!(designers.iter().map(|t| t.allowed).sum::<usize>(), 6);
+    assert_eq!(usage.truncated_branches, (BRANCHES - 5) + (5 * BRANCHES - 6));
+
+    server.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_strict_fan_out_fails_the_chain() {
+    let server = Arc::new(HAL9Server::new(config(true)));
+    server.start().await.unwrap();
+
+    let signal = NeuronSignal::forward("api-client", "planner", "API", "L4", "Plan it".into());
+    let result = run_chain(&server, signal).await;
+    assert_eq!(result.status, ChainStatus::Failed);
+    assert_eq!(result.steps_completed, 1);
+    assert!(result.errors[0].contains("exceeds the layer's maximum children"), "{:?}", result.errors);
+
+    let usage = result.fan_out.unwrap();
+    assert_eq!(usage.truncated_branches, BRANCHES);
+    assert!(usage.truncations[0].failed);
+
+    server.shutdown().await.unwrap();
+}
+
+fn designer(fan_out: FanOutConfig) -> ManagedNeuron {
+    let config = NeuronConfig {
+        id: "designer".to_string(),
+        layer: "L3".to_string(),
+        claude_command: "claude".to_string(),
+        system_prompt: None,
+        forward_connections: ids("coder-"),
+        backward_connections: vec!["planner".to_string()],
+        settings: HashMap::new(),
+    };
+    let mut neuron = ManagedNeuron::new(config, Box::new(MockClaude::new("L3", &Default::default()))).unwrap();
+    neuron.set_fan_out(Arc::new(FanOutPolicy::new(fan_out)));
+    neuron
+}
+
+#[tokio::test]
+async fn test_budget_is_split_between_kept_branches() {
+    let fan_out = FanOutConfig { node_budget: Some(100), ..Default::default() };
+    let response = "FORWARD_TO: coder-0, coder-1, coder-2\nBACKWARD_TO: planner\nERROR_TYPE: unclear\nCONTENT: Split";
+    let neuron = designer(fan_out);
+
+    let mut parent = NeuronSignal::forward("planner", "designer", "L4", "L3", "Design it".into());
+    parent.metadata.insert(FAN_OUT_BUDGET_KEY.to_string(), "9".to_string());
+    let children = neuron.parse_response(response, &parent);
+
+    // The parent's own node leaves 8 for three branches; feedback gets none
+    let budgets: Vec<_> = children.iter()
+        .map(|s| (s.propagation_type, fan_out::budget_of(&s.metadata)))
+        .collect();
+    assert_eq!(budgets, vec![
+        (PropagationType::Forward, Some(3)),
+        (PropagationType::Forward, Some(3)),
+        (PropagationType::Forward, Some(2)),
+        (PropagationType::Backward, Some(0)),
+    ]);
+    assert!(neuron.take_fan_out_truncation(&parent.signal_id).is_none());
+
+    // A signal with a spent budget forwards nowhere
+    parent.metadata.insert(FAN_OUT_BUDGET_KEY.to_string(), "1".to_string());
+    let children = neuron.parse_response(response, &parent);
+    assert!(children.iter().all(|s| s.propagation_type == PropagationType::Backward));
+    let truncation = neuron.take_fan_out_truncation(&parent.signal_id).unwrap();
+    assert_eq!((truncation.requested, truncation.allowed), (3, 0));
+}
+
+#[test]
+fn test_validate_rejects_empty_budget() {
+    assert!(fan_out::validate(&FanOutConfig::default()).is_ok());
+    let empty = FanOutConfig { node_budget: Some(0), ..Default::default() };
+    assert!(fan_out::validate(&empty).is_err());
+}
@@ -0,0 +1,224 @@
+//! Genius game broadcast: slow connections get a coalesced snapshot of the
+//! game instead of losing updates, and are closed when too far behind
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures_util::{SinkExt, StreamExt};
+use serde_json::json;
+use tokio::net::TcpListener;
+use tokio::sync::RwLock;
+use tokio_tungstenite::connect_async;
+use tokio_tungstenite::tungstenite::Message;
+
+use hal9_server::genius_game::{
+    create_genius_game_router, AppState, Board, GameConnections, GameState, GameStatus, GameType, Neuron,
+    NeuronType, Outbound, OutboundMessage, ServerMessage,
+};
+
+fn game() -> GameState {
+    GameState {
+        id: "game-1".to_string(),
+        game_type: GameType::ConsciousnessEmergence,
+        status: GameStatus::Running,
+        round: 0,
+        max_rounds: 100,
+        started_at: None,
+        players: HashMap::new(),
+        board: Board {
+            size: 19,
+            neurons: vec![],
+            connections: vec![],
+            emergence_patterns: vec![],
+            grid: vec![vec![None; 19]; 19],
+        },
+        consciousness_level: 0.0,
+        events: vec![],
+        winner: None,
+    }
+}
+
+/// Place a neuron and send the updates the server sends for it
+fn place(game: &mut GameState, tx: &Outbound) {
+    let id = game.board.neurons.len();
+    let neuron = Neuron {
+        id,
+        x: id % 19,
+        y: id / 19,
+        neuron_type: NeuronType::Processor,
+        owner: "player1".to_string(),
+        activation: 0.0,
+        processing_power: 2.0,
+        connections: vec![],
+    };
+    game.board.neurons.push(neuron.clone());
+    game.board.grid[neuron.y][neuron.x] = Some(id);
+    game.consciousness_level += 0.001;
+    tx.send(ServerMessage::NeuronPlaced { neuron, consciousness_delta: 0.001 }, game);
+    tx.send(ServerMessage::ConsciousnessUpdate { level: game.consciousness_level, patterns: vec![] }, game);
+}
+
+/// The game as a client sees it from the messages it received
+#[derive(Default)]
+struct Client {
+    neurons: Vec<usize>,
+    level: f32,
+    lagged: usize,
+    game_over: bool,
+}
+
+impl Client {
+    fn apply(&mut self, received: OutboundMessage) {
+        assert!(!self.game_over, "message after game over: {:?}", received.message);
+        match received.message {
+            ServerMessage::GameState(state) => {
+                self.neurons = state.board.neurons.iter().map(|n| n.id).collect();
+                self.level = state.consciousness_level;
+            }
+            ServerMessage::NeuronPlaced { neuron, .. } => {
+                // A delta applies on top of everything before it
+                assert!(!received.lagged);
+                assert_eq!(neuron.id, self.neurons.len(), "update out of sequence");
+                self.neurons.push(neuron.id);
+            }
+            ServerMessage::ConsciousnessUpdate { level, .. } => self.level = level,
+            ServerMessage::GameOver { .. } => self.game_over = true,
+            other => panic!("unexpected {:?}", other),
+        }
+        if received.lagged {
+            self.lagged += 1;
+        }
+    }
+}
+
+async fn next(tx: &Outbound) -> OutboundMessage {
+    tokio::time::timeout(Duration::from_secs(1), tx.recv()).await.unwrap().unwrap()
+}
+
+#[tokio::test]
+async fn test_stalled_client_receives_a_snapshot() {
+    let connections = GameConnections::new(4, 16);
+    let tx = connections.open();
+    let mut game = game();
+    for _ in 0..20 {
+        place(&mut game, &tx);
+    }
+
+    // Forty updates went out; the client is never more than the limit behind
+    let stats = connections.stats();
+    assert!(stats.connections[0].queue_depth <= 4);
+    assert!(stats.connections[0].coalesced > 0);
+
+    let first = next(&tx).await;
+    assert!(first.lagged);
+    let ServerMessage::GameState(snapshot) = &first.message else { panic!("expected a snapshot, got {:?}", first.message) };
+    assert!(snapshot.board.neurons.len() >= 16);
+
+    let mut client = Client::default();
+    client.apply(first);
+    while connections.stats().connections[0].queue_depth > 0 {
+        client.apply(next(&tx).await);
+    }
+    assert_eq!(client.neurons, (0..20).collect::<Vec<_>>());
+    assert_eq!(client.level, game.consciousness_level);
+}
+
+#[tokio::test]
+async fn test_slow_client_never_sees_a_partial_sequence() {
+    let connections = GameConnections::new(8, 64);
+    let tx = connections.open();
+    let mut game = game();
+    let mut client = Client::default();
+
+    // The client reads one message for every three neurons placed
+    for round in 0..300 {
+        place(&mut game, &tx);
+        if round % 3 == 0 {
+            client.apply(next(&tx).await);
+        }
+    }
+    game.status = GameStatus::Finished;
+    tx.send(ServerMessage::GameOver { winner: "Draw".to_string(), final_scores: HashMap::new() }, &game);
+    while !client.game_over {
+        client.apply(next(&tx).await);
+    }
+
+    assert_eq!(client.neurons, (0..300).collect::<Vec<_>>());
+    assert_eq!(client.level, game.consciousness_level);
+    assert!(client.lagged > 1);
+    let stats = connections.stats();
+    assert_eq!(stats.coalesced, stats.connections[0].coalesced);
+    assert!(stats.coalesced >= client.lagged as u64);
+    assert_eq!(stats.lag_disconnects, 0);
+}
+
+#[tokio::test]
+async fn test_client_too_far_behind_is_disconnected() {
+    let connections = GameConnections::new(2, 4);
+    let tx = connections.open();
+    let game = game();
+
+    // Errors are not state and are never coalesced away
+    for i in 0..4 {
+        assert!(tx.send(ServerMessage::Error { message: format!("error {}", i) }, &game));
+    }
+    assert!(!tx.send(ServerMessage::Error { message: "error 4".to_string() }, &game));
+    assert!(tx.is_closed());
+    assert!(tx.recv().await.is_none());
+    assert_eq!(connections.stats().lag_disconnects, 1);
+
+    connections.close(tx.id());
+    assert!(connections.is_empty());
+    assert_eq!(connections.stats().lag_disconnects, 1);
+}
+
+#[test]
+fn test_lagged_flag_on_the_wire() {
+    let message = OutboundMessage { message: ServerMessage::Error { message: "Invalid position".to_string() }, lagged: false };
+    assert_eq!(serde_json::to_value(&message).unwrap(), json!({"type": "Error", "message": "Invalid position"}));
+
+    let snapshot = OutboundMessage { message: ServerMessage::GameState(game()), lagged: true };
+    let value = serde_json::to_value(&snapshot).unwrap();
+    assert_eq!((value["type"].clone(), value["lagged"].clone()), (json!("GameState"), json!(true)));
+    assert_eq!(value["id"], "game-1");
+    let read: OutboundMessage = serde_json::from_value(value).unwrap();
+    assert!(read.lagged);
+    assert!(matches!(read.message, ServerMessage::GameState(state) if state.board.size == 19));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_websocket_connections_are_tracked() {
+    let connections = Arc::new(GameConnections::default());
+    let state = Arc::new(RwLock::new(AppState { games: HashMap::new(), connections: connections.clone() }));
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, create_genius_game_router(state)).await.unwrap();
+    });
+
+    let (mut socket, _) = connect_async(format!("ws://{}/genius/ws", addr)).await.unwrap();
+    let join = json!({"type": "JoinGame", "player_id": "p1", "player_type": {"type": "single_ai", "model": "gpt-4", "context_size": 8192}});
+    socket.send(Message::Text(join.to_string())).await.unwrap();
+
+    let mut types = Vec::new();
+    for _ in 0..2 {
+        let message = tokio::time::timeout(Duration::from_secs(2), socket.next()).await.unwrap().unwrap().unwrap();
+        let value: serde_json::Value = serde_json::from_str(message.to_text().unwrap()).unwrap();
+        assert!(value.get("lagged").is_none());
+        types.push(value["type"].as_str().unwrap().to_string());
+    }
+    assert_eq!(types, vec!["PlayerJoined", "GameState"]);
+    let stats = connections.stats();
+    assert_eq!(stats.connections.len(), 1);
+    assert_eq!((stats.connections[0].sent, stats.connections[0].queue_depth), (2, 0));
+
+    socket.close(None).await.unwrap();
+    for _ in 0..50 {
+        if connections.is_empty() {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    panic!("connection still registered after the client closed");
+}
@@ -0,0 +1,184 @@
+//! Tests for goal decomposition, constraint pausing and criteria evaluation
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+use hal9_core::config::GoalsConfig;
+use hal9_core::hierarchical::intelligence::{
+    decompose, Constraint, ConstraintType, Criterion, DecompositionStrategy, Goal, Measurement,
+};
+use hal9_core::{NeuronConfig, NeuronSignal};
+use hal9_server::chain_limits::ChainOwner;
+use hal9_server::chain_tracker::ChainTracker;
+use hal9_server::error::ServerResult;
+use hal9_server::goals::{GoalChainKind, GoalExecutor, GoalManager, GoalState, GOAL_ID_KEY};
+
+/// $0.01 per 1k prompt and completion tokens
+const PRICING: (f64, f64) = (0.01, 0.01);
+
+/// Starts chains without running them; tests finish them by hand
+struct RecordingExecutor {
+    tracker: Arc<ChainTracker>,
+    submitted: Mutex<Vec<NeuronSignal>>,
+}
+
+#[async_trait]
+impl GoalExecutor for RecordingExecutor {
+    async fn submit(&self, _owner: Option<&ChainOwner>, mut signal: NeuronSignal) -> ServerResult<String> {
+        let chain_id = self.tracker.start(&mut signal);
+        self.submitted.lock().await.push(signal);
+        Ok(chain_id)
+    }
+}
+
+fn neuron(id: &str, layer: &str) -> NeuronConfig {
+    NeuronConfig {
+        id: id.to_string(),
+        layer: layer.to_string(),
+        claude_command: "claude".to_string(),
+        system_prompt: None,
+        forward_connections: vec![],
+        backward_connections: vec![],
+        settings: HashMap::new(),
+    }
+}
+
+fn setup(evaluator: Option<&str>) -> (Arc<GoalManager>, Arc<RecordingExecutor>, Arc<ChainTracker>) {
+    let tracker = Arc::new(ChainTracker::new());
+    let executor = Arc::new(RecordingExecutor {
+        tracker: tracker.clone(),
+        submitted: Mutex::new(Vec::new()),
+    });
+    let config = GoalsConfig {
+        evaluator: evaluator.map(str::to_string),
+        ..Default::default()
+    };
+    let manager = Arc::new(GoalManager::new(
+        config,
+        &[neuron("strategist", "L5"), neuron("planner", "L4"), neuron("critic", "L3")],
+        PRICING,
+        tracker.clone(),
+        executor.clone(),
+    ));
+    manager.start();
+    (manager, executor, tracker)
+}
+
+fn criterion(name: &str, target_value: f32) -> Criterion {
+    Criterion {
+        name: name.to_string(),
+        measurement: Measurement::Absolute,
+        target_value,
+    }
+}
+
+/// Finish the chain rooted at `signal` in one step, spending `tokens`
+fn finish(tracker: &ChainTracker, signal: &NeuronSignal, output: &str, tokens: u32) {
+    tracker.record_usage(signal, tokens, tokens);
+    tracker.record_step(signal, Ok(output), 0);
+}
+
+async fn submitted(executor: &RecordingExecutor, count: usize) -> Vec<NeuronSignal> {
+    for _ in 0..50 {
+        let signals = executor.submitted.lock().await.clone();
+        if signals.len() >= count {
+            return signals;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    executor.submitted.lock().await.clone()
+}
+
+async fn wait_for_state(manager: &GoalManager, goal_id: Uuid, state: GoalState) {
+    for _ in 0..50 {
+        if manager.status(goal_id).unwrap().state == state {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    panic!("goal never reached {:?}: {:?}", state, manager.status(goal_id));
+}
+
+#[tokio::test]
+async fn test_cost_constraint_pauses_goal_mid_way() {
+    let (manager, executor, tracker) = setup(None);
+    let goal = Goal {
+        id: Uuid::new_v4(),
+        description: "Ship the billing service".to_string(),
+        priority: 0.8,
+        constraints: vec![Constraint {
+            constraint_type: ConstraintType::Resource { max_cost: 0.05 },
+            parameters: HashMap::new(),
+        }],
+        success_criteria: vec![criterion("coverage", 80.0), criterion("latency", 1.0), criterion("docs", 1.0)],
+        decomposition_strategy: DecompositionStrategy::Sequential,
+    };
+    let plan = decompose(&goal);
+    let status = manager.create(goal.clone(), plan, None).await.unwrap();
+    assert_eq!(status.state, GoalState::Active);
+    assert_eq!(status.chains.len(), 1);
+    assert_eq!(status.pending_tasks, 2);
+    assert_eq!(status.max_cost_usd, Some(0.05));
+
+    // The first task stays within budget, so the second is released
+    let signals = submitted(&executor, 1).await;
+    assert_eq!(signals[0].to_neuron, "planner");
+    assert_eq!(signals[0].metadata.get(GOAL_ID_KEY), Some(&goal.id.to_string()));
+    finish(&tracker, &signals[0], "tests written", 1_000);
+    let signals = submitted(&executor, 2).await;
+    assert_eq!(signals.len(), 2);
+
+    // The second task overspends: the goal pauses with one task never started
+    finish(&tracker, &signals[1], "latency tuned", 2_000);
+    wait_for_state(&manager, goal.id, GoalState::Paused).await;
+
+    let status = manager.status(goal.id).unwrap();
+    assert!((status.cost_usd - 0.06).abs() < 1e-9);
+    assert_eq!(status.pending_tasks, 1);
+    assert_eq!(status.violations.len(), 1);
+    assert!(status.violations[0].contains("exceeds budget"));
+    assert!(status.progress > 0.0 && status.progress < 1.0);
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert_eq!(executor.submitted.lock().await.len(), 2);
+}
+
+#[tokio::test]
+async fn test_evaluation_marks_goal_achieved() {
+    let (manager, executor, tracker) = setup(Some("critic"));
+    let goal = Goal {
+        id: Uuid::new_v4(),
+        description: "Reduce checkout latency".to_string(),
+        priority: 0.5,
+        constraints: vec![],
+        success_criteria: vec![criterion("latency_improvement", 0.3), criterion("error_rate_ok", 1.0)],
+        decomposition_strategy: DecompositionStrategy::Hierarchical,
+    };
+    let plan = decompose(&goal);
+    manager.create(goal.clone(), plan, None).await.unwrap();
+
+    let signals = submitted(&executor, 1).await;
+    assert_eq!(signals[0].to_neuron, "strategist");
+    finish(&tracker, &signals[0], "Cached the pricing lookups", 100);
+
+    // The finished task is handed to the evaluator
+    let signals = submitted(&executor, 2).await;
+    assert_eq!(signals[1].to_neuron, "critic");
+    assert!(signals[1].payload.activation.content.contains("Cached the pricing lookups"));
+    let status = manager.status(goal.id).unwrap();
+    assert_eq!(status.state, GoalState::Active);
+    assert_eq!(status.chains[1].kind, GoalChainKind::Evaluation);
+
+    finish(&tracker, &signals[1], "latency_improvement: 0.42\nerror_rate_ok: 1", 50);
+    wait_for_state(&manager, goal.id, GoalState::Achieved).await;
+
+    let status = manager.status(goal.id).unwrap();
+    assert!(status.criteria.iter().all(|criterion| criterion.met));
+    assert_eq!(status.criteria[0].value, Some(0.42));
+    assert_eq!(status.progress, 1.0);
+    assert!(status.violations.is_empty());
+}
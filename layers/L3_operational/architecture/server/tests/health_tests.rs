@@ -0,0 +1,238 @@
+//! Dependency probes and aggregate health status
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use hal9_core::config::{ClaudeConfig, HealthConfig};
+use hal9_core::memory::{MemoryStore, SqliteMemoryStore};
+use hal9_server::health::{
+    ClaudeProbe, ComponentHealth, DatabaseProbe, DiskProbe, HealthChecker, HealthProbe, HealthStatus,
+    MemoryBackendProbe,
+};
+use hal9_server::metrics::Metrics;
+use sqlx::SqlitePool;
+
+/// Disk thresholds no real volume crosses, so tests don't depend on the host
+fn config() -> HealthConfig {
+    HealthConfig {
+        disk_degraded_percent: 100.0,
+        disk_unhealthy_percent: 100.0,
+        ..Default::default()
+    }
+}
+
+fn claude(mode: &str, fallback_to_mock: bool) -> ClaudeConfig {
+    ClaudeConfig {
+        mode: mode.to_string(),
+        fallback_to_mock,
+        ..Default::default()
+    }
+}
+
+/// Claude settings pointing at a port nothing listens on
+fn unreachable() -> HealthConfig {
+    HealthConfig {
+        claude_endpoint: "http://127.0.0.1:1".to_string(),
+        ..config()
+    }
+}
+
+async fn memory_pool() -> SqlitePool {
+    SqlitePool::connect("sqlite::memory:").await.unwrap()
+}
+
+async fn memory_store(dir: &tempfile::TempDir, file: &str) -> (Arc<dyn MemoryStore>, String) {
+    let path = dir.path().join(file).display().to_string();
+    let store = SqliteMemoryStore::new(&path).await.unwrap();
+    store.initialize().await.unwrap();
+    (Arc::new(store), path)
+}
+
+/// Every dependency up, with the given probes swapped in
+async fn checker(config: HealthConfig, dir: &tempfile::TempDir, overrides: Vec<Arc<dyn HealthProbe>>) -> HealthChecker {
+    let checker = HealthChecker::new(config.clone());
+    let (store, _) = memory_store(dir, "baseline.db").await;
+    checker.register(Arc::new(DatabaseProbe::new(memory_pool().await, &config)));
+    checker.register(Arc::new(ClaudeProbe::new(&claude("mock", false), &config, Arc::new(Metrics::new()))));
+    checker.register(Arc::new(MemoryBackendProbe::new(store)));
+    checker.register(Arc::new(DiskProbe::new(vec![dir.path().to_path_buf()], &config)));
+    for probe in overrides {
+        checker.register(probe);
+    }
+    checker
+}
+
+#[tokio::test]
+async fn test_all_dependencies_healthy() {
+    let dir = tempfile::tempdir().unwrap();
+    let report = checker(config(), &dir, vec![]).await.check().await;
+
+    assert_eq!(report.status, HealthStatus::Healthy, "{:?}", report.components);
+    assert_eq!(report.components.len(), 4);
+    assert!(report.is_ready());
+    assert_eq!(report.component("claude").unwrap().metadata["mode"], "mock");
+}
+
+#[tokio::test]
+async fn test_database_down_is_unhealthy() {
+    let dir = tempfile::tempdir().unwrap();
+    let pool = memory_pool().await;
+    pool.close().await;
+    let probe = Arc::new(DatabaseProbe::new(pool, &config()));
+
+    let report = checker(config(), &dir, vec![probe]).await.check().await;
+    assert_eq!(report.status, HealthStatus::Unhealthy);
+    assert!(!report.is_ready());
+    let database = report.component("database").unwrap();
+    assert!(database.message.as_ref().unwrap().starts_with("Database query failed"));
+}
+
+#[tokio::test]
+async fn test_slow_database_is_degraded() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = HealthConfig { database_slow_ms: 0, ..config() };
+    let probe = Arc::new(DatabaseProbe::new(memory_pool().await, &config));
+
+    let report = checker(config.clone(), &dir, vec![probe]).await.check().await;
+    // A query always takes some time, so every round trip counts as slow
+    assert_eq!(report.component("database").unwrap().status, HealthStatus::Degraded);
+    assert_eq!(report.status, HealthStatus::Degraded);
+}
+
+#[tokio::test]
+async fn test_claude_unreachable() {
+    let dir = tempfile::tempdir().unwrap();
+    let metrics = Arc::new(Metrics::new());
+
+    // Without a fallback the provider is critical
+    let probe = Arc::new(ClaudeProbe::new(&claude("api", false), &unreachable(), metrics.clone()));
+    let report = checker(config(), &dir, vec![probe]).await.check().await;
+    assert_eq!(report.status, HealthStatus::Unhealthy);
+    assert!(report.component("claude").unwrap().message.as_ref().unwrap().contains("unreachable"));
+
+    // The mock keeps answering
+    let probe = Arc::new(ClaudeProbe::new(&claude("api", true), &unreachable(), metrics.clone()));
+    let report = checker(config(), &dir, vec![probe]).await.check().await;
+    assert_eq!(report.status, HealthStatus::Degraded);
+    assert!(report.is_ready());
+
+    // A recent response proves reachability without a request
+    metrics.record_provider_success();
+    let probe = Arc::new(ClaudeProbe::new(&claude("api", false), &unreachable(), metrics));
+    let report = checker(config(), &dir, vec![probe]).await.check().await;
+    assert_eq!(report.status, HealthStatus::Healthy);
+    assert!(report.component("claude").unwrap().metadata.contains_key("last_success_secs"));
+}
+
+#[tokio::test]
+async fn test_memory_backend_failure_degrades() {
+    let dir = tempfile::tempdir().unwrap();
+    let (store, path) = memory_store(&dir, "memory.db").await;
+
+    // Break the store behind its back
+    let pool = SqlitePool::connect(&format!("sqlite:{}", path)).await.unwrap();
+    sqlx::query("DROP TABLE memories").execute(&pool).await.unwrap();
+
+    let probe = Arc::new(MemoryBackendProbe::new(store));
+    let report = checker(config(), &dir, vec![probe]).await.check().await;
+    assert_eq!(report.component("memory").unwrap().status, HealthStatus::Unhealthy);
+    assert_eq!(report.status, HealthStatus::Degraded);
+    assert!(report.is_ready());
+}
+
+#[tokio::test]
+async fn test_disk_checks() {
+    let dir = tempfile::tempdir().unwrap();
+
+    let missing = Arc::new(DiskProbe::new(vec![dir.path().join("missing")], &config()));
+    let report = checker(config(), &dir, vec![missing]).await.check().await;
+    assert_eq!(report.status, HealthStatus::Unhealthy);
+    assert!(report.component("disk").unwrap().message.as_ref().unwrap().contains("failed to check disk space"));
+
+    let nearly_full = HealthConfig { disk_degraded_percent: 0.0, ..config() };
+    let probe = Arc::new(DiskProbe::new(vec![dir.path().to_path_buf()], &nearly_full));
+    let report = checker(config(), &dir, vec![probe]).await.check().await;
+    assert_eq!(report.status, HealthStatus::Degraded);
+}
+
+struct SlowProbe {
+    calls: AtomicUsize,
+    delay: Duration,
+}
+
+impl SlowProbe {
+    fn new(delay: Duration) -> Arc<Self> {
+        Arc::new(Self { calls: AtomicUsize::new(0), delay })
+    }
+}
+
+#[async_trait]
+impl HealthProbe for SlowProbe {
+    fn name(&self) -> &'static str {
+        "slow"
+    }
+
+    fn critical(&self) -> bool {
+        true
+    }
+
+    async fn check(&self) -> ComponentHealth {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        tokio::time::sleep(self.delay).await;
+        ComponentHealth::new("slow", HealthStatus::Healthy, None)
+    }
+}
+
+#[tokio::test]
+async fn test_probe_timeout() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = HealthConfig {
+        timeouts_ms: HashMap::from([("slow".to_string(), 50)]),
+        ..config()
+    };
+    let checker = checker(config, &dir, vec![]).await;
+    checker.register(SlowProbe::new(Duration::from_secs(5)));
+
+    let report = checker.check().await;
+    assert_eq!(report.status, HealthStatus::Unhealthy);
+    let slow = report.component("slow").unwrap();
+    assert_eq!(slow.message.as_deref(), Some("Check timed out after 50ms"));
+    assert_eq!(slow.latency_ms, 50);
+}
+
+#[tokio::test]
+async fn test_results_cached_for_ttl() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = HealthConfig { cache_ttl_ms: 200, ..config() };
+    let checker = checker(config, &dir, vec![]).await;
+    let probe = SlowProbe::new(Duration::ZERO);
+    checker.register(probe.clone());
+
+    checker.check().await;
+    checker.check().await;
+    assert_eq!(probe.calls.load(Ordering::SeqCst), 1);
+
+    tokio::time::sleep(Duration::from_millis(250)).await;
+    checker.check().await;
+    assert_eq!(probe.calls.load(Ordering::SeqCst), 2);
+}
+
+#[cfg(feature = "plugins")]
+#[test]
+fn test_plugin_runtime_health() {
+    use hal9_server::health::plugin_runtime_health;
+    use hal9_server::plugins::manager::PluginState;
+
+    assert_eq!(plugin_runtime_health(&[]).status, HealthStatus::Healthy);
+    assert_eq!(plugin_runtime_health(&[PluginState::Active, PluginState::Inactive]).status, HealthStatus::Healthy);
+
+    let some_failed = plugin_runtime_health(&[PluginState::Active, PluginState::Failed("trap".to_string())]);
+    assert_eq!(some_failed.status, HealthStatus::Degraded);
+    assert_eq!(some_failed.message.as_deref(), Some("1 of 2 plugins failed: trap"));
+
+    let all_failed = plugin_runtime_health(&[PluginState::Failed("trap".to_string())]);
+    assert_eq!(all_failed.status, HealthStatus::Unhealthy);
+}
@@ -0,0 +1,284 @@
+//! Ingestion sources: signed webhooks and Kafka topics starting chains,
+//! with duplicates absorbed, malformed messages quarantined and held
+//! messages refused until they may be taken in
+
+mod common;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::{
+    body::Body,
+    http::{header, Request, StatusCode},
+    Router,
+};
+use serde_json::{json, Value};
+
+use hal9_core::config::{CostTagConfig, IngestionConfig};
+use hal9_core::ServerConfig;
+use hal9_server::api::create_api_router;
+use hal9_server::chain_tracker::ChainStatus;
+use hal9_server::ingestion::{self, SOURCE_KEY};
+use hal9_server::server::HAL9Server;
+use hal9_server::webhooks;
+
+use common::{call, send};
+
+/// A signed issue delivery, as an issue tracker would send it
+const FIXTURE: &[u8] = include_bytes!("fixtures/issue_webhook.json");
+
+const SECRET: &str = "issue-tracker-secret";
+
+fn config(quarantine_path: &str, source: Value) -> ServerConfig {
+    common::mock_config(json!({
+        "server_id": "ingestion-test",
+        "neurons": [
+            {"id": "planner", "layer": "L4", "forward_connections": ["coder"], "backward_connections": []},
+            {"id": "coder", "layer": "L2", "forward_connections": [], "backward_connections": ["planner"]},
+        ],
+        "claude": {
+            "mock_responses": {
+                "L4": [{"trigger": "default", "response": "FORWARD_TO: coder\nCONTENT: Fix the discount", "delay_ms": 0}],
+                "L2": [{"trigger": "default", "response": "RESULT: Fixed", "delay_ms": 0}],
+            },
+            "cost_controls": {"tags": {"allowed_keys": ["source", "team"]}},
+        },
+        "memory": {"enabled": false},
+        "ingestion": {"quarantine_path": quarantine_path, "sources": [source]},
+    }))
+}
+
+fn mapping() -> Value {
+    json!({
+        "to": "planner",
+        "content": "Triage #{{/issue/number}}: {{/issue/title}}",
+        "tags": {"team": "{{/team}}"},
+        "dedup_key": "{{/delivery}}",
+    })
+}
+
+async fn deliver(app: &Router, body: &[u8], signature: Option<String>) -> (StatusCode, Option<String>, Value) {
+    let timestamp = chrono::Utc::now().timestamp();
+    let signature = signature.unwrap_or_else(|| webhooks::sign(SECRET, timestamp, body));
+    let request = Request::builder()
+        .method("POST")
+        .uri("/api/v1/ingest/webhooks/issues")
+        .header(header::CONTENT_TYPE, "application/json")
+        .header("X-Signature-256", signature)
+        .header("X-Hal9-Timestamp", timestamp.to_string())
+        .body(Body::from(body.to_vec()))
+        .unwrap();
+    let reply = send(app, request).await;
+    let retry_after = reply.header(header::RETRY_AFTER).map(str::to_string);
+    (reply.status, retry_after, reply.json())
+}
+
+/// A copy of the fixture delivered under another delivery ID
+fn redelivery(delivery: &str) -> Vec<u8> {
+    let mut message: Value = serde_json::from_slice(FIXTURE).unwrap();
+    message["delivery"] = json!(delivery);
+    serde_json::to_vec(&message).unwrap()
+}
+
+async fn finished(server: &HAL9Server, chain_id: &str) {
+    tokio::time::timeout(Duration::from_secs(10), async {
+        while server.get_chain_result(chain_id).await.unwrap().status == ChainStatus::Running {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("chain did not finish");
+}
+
+#[tokio::test]
+async fn test_signed_webhook_starts_a_tagged_chain_once() {
+    std::env::set_var("HAL9_TEST_INGEST_WEBHOOK_SECRET", SECRET);
+    let dir = tempfile::tempdir().unwrap();
+    let source = json!({
+        "name": "issues",
+        "transport": {
+            "type": "webhook",
+            "secret_env": "HAL9_TEST_INGEST_WEBHOOK_SECRET",
+            "timestamp_header": "X-Hal9-Timestamp",
+        },
+        "mapping": mapping(),
+        "tags": {"source": "issues"},
+        "max_per_minute": 2,
+    });
+    let quarantine_path = dir.path().join("quarantine.db");
+    let server = Arc::new(HAL9Server::new(config(quarantine_path.to_str().unwrap(), source)));
+    server.start().await.unwrap();
+    let app = create_api_router(server.clone());
+
+    // The delivery starts a chain carrying the source and its tags
+    let (status, _, body) = deliver(&app, FIXTURE, None).await;
+    assert_eq!(status, StatusCode::ACCEPTED, "{}", body);
+    assert_eq!(body["data"]["outcome"], "accepted");
+    let chain_id = body["data"]["chain_id"].as_str().unwrap().to_string();
+    finished(&server, &chain_id).await;
+    let result = server.get_chain_result(&chain_id).await.unwrap();
+    assert_eq!(result.input, "Triage #4182: Checkout total ignores discount codes");
+    assert_eq!(result.steps_completed, 2);
+    assert_eq!(serde_json::to_value(&result.tags).unwrap(), json!({"source": "issues", "team": "growth"}));
+    let record = server.chain_tracker().get(&chain_id).unwrap();
+    assert_eq!(record.root_metadata[SOURCE_KEY], "issues");
+    assert_eq!(record.root_metadata[ingestion::MESSAGE_KEY_KEY], "7f3c2a10-5b8e-4f6a-9d21-0c4e8b1a2d33");
+
+    // A retried delivery is answered with the same chain
+    let (status, _, body) = deliver(&app, FIXTURE, None).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["data"], json!({"outcome": "duplicate", "chain_id": chain_id}));
+
+    // A bad signature is refused before anything is read
+    let (status, _, _) = deliver(&app, FIXTURE, Some("sha256=00".to_string())).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    // A message lacking what the mapping needs is quarantined with the reason
+    let (status, _, body) = deliver(&app, br#"{"delivery": "d-2", "team": "growth"}"#, None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST, "{}", body);
+    let quarantine_id = body["error"]["details"]["quarantine_id"].as_str().unwrap().to_string();
+    let (status, body) = call(&app, "GET", "/api/v1/admin/ingestion/quarantine?source=issues", None).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    let items = body["data"]["items"].as_array().unwrap();
+    assert_eq!(items.len(), 1, "{}", body);
+    assert_eq!(items[0]["id"], quarantine_id.as_str());
+    assert_eq!(items[0]["error"], "Message has no value at /issue/number");
+    assert!(quarantine_path.exists());
+
+    // Two chains a minute: the second is taken in, the third waits
+    let (status, _, _) = deliver(&app, &redelivery("d-3"), None).await;
+    assert_eq!(status, StatusCode::ACCEPTED);
+    let (status, retry_after, body) = deliver(&app, &redelivery("d-4"), None).await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS, "{}", body);
+    assert!(retry_after.is_some());
+    assert_eq!(body["error"]["details"]["reason"], "rate_limited");
+
+    // Nothing is taken in while paused, not even a fresh delivery
+    let (status, body) = call(&app, "POST", "/api/v1/admin/ingestion/issues/pause", None).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["data"]["paused"], true);
+    let (status, retry_after, _) = deliver(&app, &redelivery("d-5"), None).await;
+    assert_eq!((status, retry_after.as_deref()), (StatusCode::SERVICE_UNAVAILABLE, Some("5")));
+    let (status, body) = call(&app, "POST", "/api/v1/admin/ingestion/issues/resume", None).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["data"]["paused"], false);
+
+    let (_, body) = call(&app, "GET", "/api/v1/admin/ingestion", None).await;
+    let status = &body["data"][0];
+    assert_eq!(status["transport"], "webhook");
+    let counts = ["accepted", "duplicates", "quarantined", "held_messages", "rejected"].map(|key| status[key].as_u64().unwrap());
+    assert_eq!(counts, [2, 1, 1, 2, 1], "{}", status);
+    assert!(status["throughput_per_sec"].as_f64().unwrap() > 0.0);
+    assert!(status["lag"].is_null());
+
+    let snapshot = server.metrics().snapshot();
+    assert_eq!(snapshot.ingestion.len(), 1);
+    assert_eq!(snapshot.ingestion[0].accepted, 2);
+    let (status, _) = call(&app, "POST", "/api/v1/admin/ingestion/unknown/pause", None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    server.shutdown().await.unwrap();
+}
+
+#[test]
+fn test_sources_are_validated() {
+    let tags = CostTagConfig { allowed_keys: vec!["source".to_string()], ..Default::default() };
+    let parse = |sources: Value| -> IngestionConfig { serde_json::from_value(json!({"sources": sources})).unwrap() };
+    let webhook = |name: &str| {
+        json!({
+            "name": name,
+            "transport": {"type": "webhook", "secret_env": "HAL9_TEST_SECRET"},
+            "mapping": {"to": "planner", "content": "{{/text}}"},
+            "tags": {"source": name},
+        })
+    };
+
+    assert!(ingestion::validate(&parse(json!([webhook("issues"), webhook("alerts")])), &tags).is_ok());
+    assert!(ingestion::validate(&parse(json!([webhook("issues"), webhook("issues")])), &tags).is_err());
+    assert!(ingestion::validate(&parse(json!([webhook("issue tracker")])), &tags).is_err());
+
+    let mut source = webhook("issues");
+    source["mapping"]["content"] = json!("{{/text");
+    assert!(ingestion::validate(&parse(json!([source])), &tags).is_err());
+
+    // Tags must be allowed, and a budget needs tags to count against
+    let mut source = webhook("issues");
+    source["mapping"]["tags"] = json!({"team": "{{/team}}"});
+    assert!(ingestion::validate(&parse(json!([source])), &tags).is_err());
+    let mut source = webhook("issues");
+    source["budget"] = json!({"usd": 5.0});
+    assert!(ingestion::validate(&parse(json!([source.clone()])), &tags).is_ok());
+    source["tags"] = json!({});
+    assert!(ingestion::validate(&parse(json!([source])), &tags).is_err());
+
+    let kafka = json!({
+        "name": "orders",
+        "transport": {"type": "kafka", "brokers": "localhost:9092", "topic": "orders", "group": "hal9"},
+        "mapping": {"to": "planner", "content": "{{/text}}"},
+    });
+    let result = ingestion::validate(&parse(json!([kafka])), &tags);
+    assert_eq!(result.is_ok(), cfg!(feature = "kafka"), "{:?}", result);
+}
+
+#[cfg(feature = "kafka")]
+#[tokio::test]
+#[ignore = "needs Docker for the Kafka container"]
+async fn test_kafka_messages_start_chains_at_least_once() {
+    use rdkafka::producer::{FutureProducer, FutureRecord};
+    use rdkafka::ClientConfig;
+    use testcontainers::clients::Cli;
+    use testcontainers_modules::kafka::{Kafka, KAFKA_PORT};
+
+    let docker = Cli::default();
+    let kafka = docker.run(Kafka::default());
+    let brokers = format!("127.0.0.1:{}", kafka.get_host_port_ipv4(KAFKA_PORT));
+
+    // The fixture twice, as a producer retrying it would, and a broken message
+    let producer: FutureProducer = ClientConfig::new()
+        .set("bootstrap.servers", &brokers)
+        .set("message.timeout.ms", "10000")
+        .create()
+        .unwrap();
+    for payload in [FIXTURE, FIXTURE, b"not json".as_slice()] {
+        producer
+            .send(FutureRecord::<(), _>::to("issues").payload(payload), Duration::from_secs(10))
+            .await
+            .unwrap();
+    }
+
+    let dir = tempfile::tempdir().unwrap();
+    let source = json!({
+        "name": "issues",
+        "transport": {"type": "kafka", "brokers": brokers, "topic": "issues", "group": "hal9-ingestion-test"},
+        "mapping": mapping(),
+        "tags": {"source": "issues"},
+    });
+    let server = Arc::new(HAL9Server::new(config(dir.path().join("quarantine.db").to_str().unwrap(), source)));
+    let mut finished = server.chain_tracker().subscribe();
+    server.start().await.unwrap();
+
+    // One chain for the two copies of the fixture
+    let result = tokio::time::timeout(Duration::from_secs(60), finished.recv())
+        .await
+        .expect("no chain was started from the topic")
+        .unwrap();
+    assert_eq!(result.input, "Triage #4182: Checkout total ignores discount codes");
+    assert_eq!(server.chain_tracker().get(&result.chain_id).unwrap().root_metadata[SOURCE_KEY], "issues");
+
+    let status = tokio::time::timeout(Duration::from_secs(30), async {
+        loop {
+            let status = server.ingestion_status().await.remove(0);
+            if status.accepted + status.duplicates + status.quarantined == 3 && status.lag == Some(0) {
+                return status;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+    })
+    .await
+    .expect("messages were not all handled");
+    assert_eq!((status.accepted, status.duplicates, status.quarantined), (1, 1, 1));
+    let quarantined = server.quarantined_messages(Some("issues")).await.unwrap();
+    assert!(quarantined[0].error.contains("not JSON"), "{:?}", quarantined);
+
+    server.shutdown().await.unwrap();
+}
@@ -0,0 +1,150 @@
+//! Neurons isolated in worker processes: crashes, restarts and limits
+
+mod common;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde_json::json;
+
+use hal9_core::neuron::NeuronState;
+use hal9_core::{NeuronInterface, NeuronSignal, ServerConfig};
+use hal9_server::api::WsMessage;
+use hal9_server::isolation::{WorkerEventKind, WORKER_CRASHED};
+use hal9_server::server::HAL9Server;
+
+fn config(mock_delay_ms: u64, max_memory_mb: u64) -> ServerConfig {
+    let neuron = |id: &str, isolation: &str| json!({
+        "id": id,
+        "layer": "L2",
+        "forward_connections": [],
+        "backward_connections": [],
+        "settings": {"isolation": isolation},
+    });
+    common::mock_config(json!({
+        "server_id": "isolation-test",
+        "neurons": [neuron("isolated", "process"), neuron("shared", "in_process")],
+        "claude": {
+            "mock_responses": {"L2": [{"trigger": "default", "response": "RESULT: implemented", "delay_ms": mock_delay_ms}]},
+        },
+        "isolation": {
+            "worker_command": env!("CARGO_BIN_EXE_hal9-neuron-worker"),
+            "max_memory_mb": max_memory_mb,
+            "restart_initial_backoff_ms": 500,
+        },
+    }))
+}
+
+fn signal(content: String) -> NeuronSignal {
+    NeuronSignal::forward("design", "isolated", "L3", "L2", content)
+}
+
+async fn wait_for_restarts(server: &HAL9Server, neuron_id: &str, restarts: u32) {
+    let neuron = server.registry().get(neuron_id).unwrap();
+    let worker = neuron.worker().unwrap();
+    for _ in 0..100 {
+        if worker.restarts() >= restarts && worker.is_up() {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    panic!("worker for {} did not restart", neuron_id);
+}
+
+#[tokio::test]
+async fn test_killed_worker_dead_letters_signal_and_restarts() {
+    let server = Arc::new(HAL9Server::new(config(3000, 1024)));
+    server.start().await.unwrap();
+    let mut events = server.subscribe_to_events().await;
+    let mut worker_events = server.subscribe_worker_events();
+
+    let neuron = server.registry().get("isolated").unwrap();
+    let worker = neuron.worker().unwrap().clone();
+    let pid = worker.pid();
+    assert_ne!(pid, 0);
+    assert_ne!(pid, std::process::id());
+
+    // Kill the worker while the mock is still answering the signal
+    let lost = signal("build the parser".to_string());
+    let processing = {
+        let neuron = neuron.clone();
+        let lost = lost.clone();
+        tokio::spawn(async move { neuron.process_signal(&lost).await })
+    };
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    let killed = std::process::Command::new("kill").args(["-9", &pid.to_string()]).status().unwrap();
+    assert!(killed.success());
+
+    let err = processing.await.unwrap().unwrap_err();
+    assert!(err.to_string().contains("exited"), "{}", err);
+    let dead_letters = server.dead_letters();
+    assert_eq!(dead_letters.len(), 1);
+    assert_eq!(dead_letters[0].neuron_id, "isolated");
+    assert_eq!(dead_letters[0].reason, WORKER_CRASHED);
+    assert_eq!(dead_letters[0].signal.signal_id, lost.signal_id);
+    // Down until the restart backoff has passed
+    assert_eq!(neuron.health().await.unwrap().state, NeuronState::Failed);
+
+    wait_for_restarts(&server, "isolated", 1).await;
+    assert_ne!(worker.pid(), pid);
+    assert_eq!(worker.restarts(), 1);
+    assert_eq!(neuron.health().await.unwrap().state, NeuronState::Running);
+
+    let crashed = worker_events.recv().await.unwrap();
+    assert!(matches!(crashed.kind, WorkerEventKind::Crashed { lost_signals: 1, .. }), "{:?}", crashed);
+    let restarted = worker_events.recv().await.unwrap();
+    assert_eq!(restarted.kind, WorkerEventKind::Restarted { pid: worker.pid() });
+    assert_eq!(restarted.restarts, 1);
+    let mut changes = Vec::new();
+    while let Ok(event) = events.try_recv() {
+        if let WsMessage::NeuronStateChange { neuron_id, old_state, new_state } = event {
+            changes.push((neuron_id, old_state, new_state));
+        }
+    }
+    assert_eq!(changes, vec![
+        ("isolated".to_string(), "Running".to_string(), "Failed".to_string()),
+        ("isolated".to_string(), "Failed".to_string(), "Running".to_string()),
+    ]);
+
+    // The new worker picks up where the old one left off
+    let response = neuron.process_signal(&signal("build the lexer".to_string())).await.unwrap();
+    assert!(!response.is_empty());
+    let health = neuron.health().await.unwrap();
+    assert_eq!((health.signals_processed, health.errors_count), (1, 1));
+
+    server.shutdown().await.unwrap();
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test]
+async fn test_memory_limit_breach_terminates_only_the_worker() {
+    let server = Arc::new(HAL9Server::new(config(10, 128)));
+    server.start().await.unwrap();
+    let isolated = server.registry().get("isolated").unwrap();
+    let shared = server.registry().get("shared").unwrap();
+    let pid = isolated.worker().unwrap().pid();
+
+    // Reading a signal larger than the worker's data limit fails its
+    // allocation, which aborts the worker
+    let oversized = signal("x".repeat(192 * 1024 * 1024));
+    let err = isolated.process_signal(&oversized).await.unwrap_err();
+    assert!(err.to_string().contains("exited"), "{}", err);
+    let dead_letters = server.dead_letters();
+    assert_eq!(dead_letters.len(), 1);
+    assert_eq!(dead_letters[0].reason, WORKER_CRASHED);
+    drop(oversized);
+
+    // The server and its in-process neurons carry on
+    let response = shared.process_signal(&NeuronSignal::forward(
+        "design", "shared", "L3", "L2", "build the parser".to_string(),
+    )).await.unwrap();
+    assert!(!response.is_empty());
+    assert_eq!(shared.health().await.unwrap().state, NeuronState::Running);
+
+    wait_for_restarts(&server, "isolated", 1).await;
+    assert_ne!(isolated.worker().unwrap().pid(), pid);
+    let response = isolated.process_signal(&signal("build the lexer".to_string())).await.unwrap();
+    assert!(!response.is_empty());
+
+    server.shutdown().await.unwrap();
+}
@@ -0,0 +1,117 @@
+//! Tests for layer pauses: holding, releasing, spilling and persistence
+
+use std::time::Duration;
+
+use chrono::Utc;
+use tokio::sync::mpsc;
+
+use hal9_core::config::LayerPauseConfig;
+use hal9_core::NeuronSignal;
+use hal9_server::error::ServerError;
+use hal9_server::layer_pause::{LayerAdmission, LayerGate};
+
+fn config(dir: &tempfile::TempDir, max_held: usize) -> LayerPauseConfig {
+    LayerPauseConfig {
+        state_path: dir.path().join("layer_pauses.json").to_string_lossy().into_owned(),
+        max_held,
+    }
+}
+
+fn signal(layer: &str, content: &str) -> NeuronSignal {
+    NeuronSignal::forward("planner", "coder", "L4", layer, content.to_string())
+}
+
+#[tokio::test]
+async fn test_held_signals_are_released_in_order() {
+    let dir = tempfile::tempdir().unwrap();
+    let gate = LayerGate::load(config(&dir, 10));
+    let (tx, mut rx) = mpsc::channel(10);
+    gate.attach(tx);
+
+    gate.pause("l2", Some("runaway spend".to_string()), None, Some("admin")).unwrap();
+    assert!(gate.is_paused("L2"));
+
+    for content in ["first", "second", "third"] {
+        assert!(matches!(gate.admit(signal("L2", content)), LayerAdmission::Held));
+    }
+    // Other layers are unaffected
+    assert!(matches!(gate.admit(signal("L3", "design")), LayerAdmission::Deliver(_)));
+    assert_eq!(gate.paused()[0].held, 3);
+
+    assert_eq!(gate.resume("L2", Some("admin")).await.unwrap(), 3);
+    assert!(!gate.is_paused("L2"));
+    for expected in ["first", "second", "third"] {
+        assert_eq!(rx.recv().await.unwrap().payload.activation.content, expected);
+    }
+    assert!(matches!(gate.admit(signal("L2", "fourth")), LayerAdmission::Deliver(_)));
+}
+
+#[tokio::test]
+async fn test_signals_beyond_bound_overflow() {
+    let dir = tempfile::tempdir().unwrap();
+    let gate = LayerGate::load(config(&dir, 2));
+    let (tx, mut rx) = mpsc::channel(10);
+    gate.attach(tx);
+    gate.pause("L2", None, None, None).unwrap();
+
+    assert!(matches!(gate.admit(signal("L2", "first")), LayerAdmission::Held));
+    assert!(matches!(gate.admit(signal("L2", "second")), LayerAdmission::Held));
+    match gate.admit(signal("L2", "third")) {
+        LayerAdmission::Overflow(spilled) => assert_eq!(spilled.payload.activation.content, "third"),
+        other => panic!("expected overflow, got {:?}", other),
+    }
+    assert_eq!(gate.spilled(), 1);
+
+    // Only the held signals come back
+    assert_eq!(gate.resume("L2", None).await.unwrap(), 2);
+    assert_eq!(rx.recv().await.unwrap().payload.activation.content, "first");
+    assert_eq!(rx.recv().await.unwrap().payload.activation.content, "second");
+    assert!(rx.try_recv().is_err());
+}
+
+#[tokio::test]
+async fn test_pause_survives_restart() {
+    let dir = tempfile::tempdir().unwrap();
+    let gate = LayerGate::load(config(&dir, 10));
+    let pause = gate.pause("L3", Some("incident".to_string()), None, Some("ops")).unwrap();
+    drop(gate);
+
+    let restarted = LayerGate::load(config(&dir, 10));
+    let paused = restarted.paused();
+    assert_eq!(paused.len(), 1);
+    assert_eq!(paused[0].pause, pause);
+    assert_eq!(paused[0].held, 0);
+
+    let (tx, _rx) = mpsc::channel(1);
+    restarted.attach(tx);
+    restarted.resume("L3", None).await.unwrap();
+    assert!(LayerGate::load(config(&dir, 10)).paused().is_empty());
+}
+
+#[tokio::test]
+async fn test_expired_pause_resumes() {
+    let dir = tempfile::tempdir().unwrap();
+    let gate = LayerGate::load(config(&dir, 10));
+    let (tx, mut rx) = mpsc::channel(10);
+    gate.attach(tx);
+    gate.pause("L2", None, Some(Duration::from_secs(60)), None).unwrap();
+    gate.pause("L3", None, None, None).unwrap();
+    assert!(matches!(gate.admit(signal("L2", "held")), LayerAdmission::Held));
+
+    assert!(gate.resume_expired(Utc::now()).await.is_empty());
+    let resumed = gate.resume_expired(Utc::now() + chrono::Duration::seconds(61)).await;
+    assert_eq!(resumed, vec!["L2".to_string()]);
+    assert_eq!(rx.recv().await.unwrap().payload.activation.content, "held");
+    assert!(gate.is_paused("L3"));
+}
+
+#[tokio::test]
+async fn test_invalid_requests_are_rejected() {
+    let dir = tempfile::tempdir().unwrap();
+    let gate = LayerGate::load(config(&dir, 10));
+
+    let unknown = gate.pause("L42", None, None, None);
+    assert!(matches!(unknown, Err(ServerError::InvalidInput(_))));
+    let not_paused = gate.resume("L2", None).await;
+    assert!(matches!(not_paused, Err(ServerError::InvalidInput(_))));
+}
@@ -0,0 +1,200 @@
+//! Local model batching: batch formation, deadline bypass and fan-out
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use axum::{routing::post, Json, Router};
+use parking_lot::Mutex;
+use serde_json::{json, Value};
+use tokio::time::Instant;
+
+use hal9_core::{config::LocalModelConfig, Error, Result};
+use hal9_server::{
+    claude::{model_pricing, ClaudeInterface},
+    local_model::{with_deadline, LocalBackend, LocalCompletion, LocalModelBatcher, LocalModelClaude, OpenAiCompatibleBackend},
+    metrics::Metrics,
+};
+
+/// Upper-cases prompts, recording each batch and single request with the
+/// time it was sent. Prompts containing "fail" fail on their own.
+#[derive(Default)]
+struct RecordingBackend {
+    batches: Mutex<Vec<(Instant, Vec<String>)>>,
+    singles: Mutex<Vec<(Instant, String)>>,
+}
+
+fn answer(prompt: &str) -> Result<LocalCompletion> {
+    if prompt.contains("fail") {
+        return Err(Error::ClaudeApi(format!("cannot answer {}", prompt)));
+    }
+    Ok(LocalCompletion { text: prompt.to_uppercase(), prompt_tokens: prompt.len() as u32, completion_tokens: 1 })
+}
+
+#[async_trait]
+impl LocalBackend for RecordingBackend {
+    async fn complete(&self, prompt: &str) -> Result<LocalCompletion> {
+        self.singles.lock().push((Instant::now(), prompt.to_string()));
+        answer(prompt)
+    }
+
+    async fn complete_batch(&self, prompts: &[String]) -> Vec<Result<LocalCompletion>> {
+        self.batches.lock().push((Instant::now(), prompts.to_vec()));
+        prompts.iter().map(|prompt| answer(prompt)).collect()
+    }
+}
+
+fn batcher(backend: &Arc<RecordingBackend>, max_batch_size: usize, metrics: &Arc<Metrics>) -> Arc<LocalModelBatcher> {
+    LocalModelBatcher::new(
+        "llama",
+        backend.clone(),
+        max_batch_size,
+        Duration::from_millis(20),
+        Duration::from_millis(100),
+        Some(metrics.clone()),
+    )
+}
+
+fn submit(batcher: &Arc<LocalModelBatcher>, prompt: &str) -> tokio::task::JoinHandle<Result<LocalCompletion>> {
+    let (batcher, prompt) = (batcher.clone(), prompt.to_string());
+    tokio::spawn(async move { batcher.submit(prompt, None).await })
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_batches_fill_or_wait_out_max_wait() {
+    let backend = Arc::new(RecordingBackend::default());
+    let metrics = Arc::new(Metrics::new());
+    let batcher = batcher(&backend, 3, &metrics);
+    let start = Instant::now();
+
+    // Four requests at once: a full batch leaves without waiting, the
+    // fourth waits out the window for company
+    let handles: Vec<_> = ["a", "b", "c", "d"].iter().map(|p| submit(&batcher, p)).collect();
+    tokio::time::sleep(Duration::from_millis(10)).await;
+    assert_eq!(backend.batches.lock().len(), 1);
+    tokio::time::sleep(Duration::from_millis(5)).await;
+    // A late arrival joins the open batch
+    let late = submit(&batcher, "e");
+    for (handle, expected) in handles.into_iter().chain([late]).zip(["A", "B", "C", "D", "E"]) {
+        assert_eq!(handle.await.unwrap().unwrap().text, expected);
+    }
+
+    let batches = backend.batches.lock().clone();
+    assert_eq!(batches.len(), 2);
+    assert_eq!((batches[0].0 - start, batches[0].1.clone()), (Duration::ZERO, vec!["a".into(), "b".into(), "c".into()]));
+    assert_eq!((batches[1].0 - start, batches[1].1.clone()), (Duration::from_millis(20), vec!["d".into(), "e".into()]));
+
+    let stats = metrics.snapshot().local_batches["llama"].clone();
+    assert_eq!((stats.batches, stats.batched_requests, stats.bypassed), (2, 5, 0));
+    // Bucket bounds 1, 2, 4: one batch of two, one of three
+    assert_eq!(&stats.batch_sizes[..3], &[0, 1, 2]);
+    // a, b and c waited 0ms, d 20ms and e 5ms
+    assert_eq!(stats.queue_latency.count, 5);
+    assert!((stats.queue_latency.sum_seconds - 0.025).abs() < 1e-9, "{}", stats.queue_latency.sum_seconds);
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_signal_near_deadline_skips_batch_wait() {
+    let backend = Arc::new(RecordingBackend::default());
+    let metrics = Arc::new(Metrics::new());
+    let batcher = batcher(&backend, 8, &metrics);
+    let start = Instant::now();
+
+    let waiting = submit(&batcher, "relaxed");
+    tokio::task::yield_now().await;
+    // Within max wait plus margin (120ms) of its deadline
+    let urgent = batcher.submit("urgent".to_string(), Some(start + Duration::from_millis(110))).await.unwrap();
+    assert_eq!(urgent.text, "URGENT");
+    assert_eq!(Instant::now(), start);
+    assert_eq!(backend.singles.lock().clone(), vec![(start, "urgent".to_string())]);
+    assert!(backend.batches.lock().is_empty());
+
+    // A distant deadline waits for the batch like any other request
+    let patient = batcher.submit("patient".to_string(), Some(start + Duration::from_secs(30)));
+    let (patient, waiting) = tokio::join!(patient, waiting);
+    assert_eq!((patient.unwrap().text, waiting.unwrap().unwrap().text), ("PATIENT".to_string(), "RELAXED".to_string()));
+    assert_eq!(backend.batches.lock()[0].1, vec!["relaxed".to_string(), "patient".to_string()]);
+    assert_eq!(metrics.snapshot().local_batches["llama"].bypassed, 1);
+
+    // The provider reads the deadline of the signal being processed
+    let claude = LocalModelClaude::new(batcher.clone(), "L2");
+    let deadline = Instant::now() + Duration::from_millis(50);
+    with_deadline(deadline, claude.send_message("hurry")).await.unwrap();
+    assert_eq!(backend.singles.lock().len(), 2);
+    let usage = claude.last_token_usage().unwrap();
+    assert_eq!(usage.model.as_deref(), Some("local/llama"));
+    assert_eq!(model_pricing("local/llama"), (0.0, 0.0));
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_responses_fan_out_to_their_requesters() {
+    let backend = Arc::new(RecordingBackend::default());
+    let metrics = Arc::new(Metrics::new());
+    let batcher = batcher(&backend, 8, &metrics);
+
+    let prompts = ["alpha", "beta", "please fail", "gamma", "delta"];
+    let handles: Vec<_> = prompts.iter().map(|p| submit(&batcher, p)).collect();
+    let results = futures::future::join_all(handles).await;
+
+    assert_eq!(backend.batches.lock().len(), 1);
+    for (prompt, result) in prompts.iter().zip(results) {
+        match result.unwrap() {
+            Ok(completion) => {
+                assert_eq!(completion.text, prompt.to_uppercase());
+                assert_eq!(completion.prompt_tokens, prompt.len() as u32);
+            }
+            // Only the failing prompt fails
+            Err(e) => assert!(prompt.contains("fail") && e.to_string().contains("cannot answer please fail"), "{}", e),
+        }
+    }
+}
+
+async fn start_completions_server(requests: Arc<Mutex<Vec<Value>>>) -> String {
+    let app = Router::new().route(
+        "/v1/completions",
+        post(move |Json(body): Json<Value>| async move {
+            requests.lock().push(body.clone());
+            let prompts: Vec<String> = serde_json::from_value(body["prompt"].clone()).unwrap();
+            // Answered out of order, as servers may
+            let choices: Vec<Value> = prompts
+                .iter()
+                .enumerate()
+                .rev()
+                .map(|(index, prompt)| json!({"index": index, "text": format!("re: {}", prompt)}))
+                .collect();
+            let n = prompts.len() as u32;
+            Json(json!({"choices": choices, "usage": {"prompt_tokens": 10 * n, "completion_tokens": 4 * n}}))
+        }),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+    format!("http://{}/", addr)
+}
+
+#[tokio::test]
+async fn test_openai_compatible_batch_endpoint() {
+    let requests = Arc::new(Mutex::new(Vec::new()));
+    let base_url = start_completions_server(requests.clone()).await;
+    let config: LocalModelConfig = serde_json::from_value(json!({
+        "base_url": base_url,
+        "model": "llama",
+        "batch_endpoint": true,
+        "max_tokens": 64
+    }))
+    .unwrap();
+    let backend = OpenAiCompatibleBackend::new(&config);
+
+    let prompts = vec!["one".to_string(), "two".to_string(), "three".to_string()];
+    let completions: Vec<_> = backend.complete_batch(&prompts).await.into_iter().map(Result::unwrap).collect();
+    let texts: Vec<_> = completions.iter().map(|c| c.text.as_str()).collect();
+    assert_eq!(texts, vec!["re: one", "re: two", "re: three"]);
+    assert!(completions.iter().all(|c| (c.prompt_tokens, c.completion_tokens) == (10, 4)));
+
+    // One request carrying every prompt
+    let requests = requests.lock().clone();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0], json!({"model": "llama", "prompt": ["one", "two", "three"], "max_tokens": 64}));
+}
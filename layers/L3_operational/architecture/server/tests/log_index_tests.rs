@@ -0,0 +1,130 @@
+//! Log index queries, runtime level changes and buffer bounds
+
+use std::sync::Arc;
+
+use hal9_core::config::LogIndexConfig;
+use hal9_server::log_index::{LogIndex, LogQuery};
+use hal9_server::logging::LogLevelControl;
+use tracing_subscriber::layer::SubscriberExt;
+
+fn index(capacity: usize) -> Arc<LogIndex> {
+    Arc::new(LogIndex::new(&LogIndexConfig {
+        enabled: true,
+        capacity,
+        retention_hours: 24,
+    }))
+}
+
+/// Run `f` with a subscriber filtering at `base` and feeding `index`
+fn with_logging(index: &Arc<LogIndex>, base: &str, f: impl FnOnce(&LogLevelControl)) {
+    let (filter, control) = LogLevelControl::new(base);
+    let subscriber = tracing_subscriber::registry()
+        .with(filter)
+        .with(index.layer());
+    tracing::subscriber::with_default(subscriber, || f(&control));
+}
+
+#[test]
+fn test_query_filters() {
+    let index = index(1000);
+    with_logging(&index, "trace", |_| {
+        let span = tracing::info_span!("signal", chain_id = "chain-a", neuron_id = "neuron-1", layer = "L4");
+        span.in_scope(|| {
+            tracing::info!(event = "signal_processed", "processed");
+            tracing::warn!("slow neuron");
+        });
+        
+        tracing::info_span!("signal", chain_id = "chain-b").in_scope(|| {
+            tracing::error!(event = "neuron_error", "failed");
+        });
+        tracing::info!("unrelated");
+    });
+    
+    let chain_a = index.query(&LogQuery { chain_id: Some("chain-a".to_string()), ..Default::default() });
+    assert_eq!(chain_a.len(), 2);
+    assert_eq!(chain_a[0].message, "processed");
+    assert_eq!(chain_a[0].event.as_deref(), Some("signal_processed"));
+    assert_eq!(chain_a[0].neuron_id.as_deref(), Some("neuron-1"));
+    assert_eq!(chain_a[0].layer.as_deref(), Some("L4"));
+    
+    // Minimum level includes anything more severe
+    let warnings = index.query(&LogQuery { level: Some("warn".to_string()), ..Default::default() });
+    let messages: Vec<_> = warnings.iter().map(|e| e.message.as_str()).collect();
+    assert_eq!(messages, vec!["slow neuron", "failed"]);
+    
+    let chain_a_errors = index.query(&LogQuery {
+        chain_id: Some("chain-a".to_string()),
+        level: Some("error".to_string()),
+        ..Default::default()
+    });
+    assert!(chain_a_errors.is_empty());
+    
+    // Everything was logged just now
+    assert_eq!(index.query(&LogQuery { since: Some("1h".to_string()), ..Default::default() }).len(), 4);
+    assert!(index.query(&LogQuery { since: Some("2999-01-01T00:00:00Z".to_string()), ..Default::default() }).is_empty());
+    
+    // Limit keeps the newest entries
+    let newest = index.query(&LogQuery { limit: Some(1), ..Default::default() });
+    assert_eq!(newest[0].message, "unrelated");
+}
+
+#[test]
+fn test_level_change_takes_effect_without_restart() {
+    let index = index(1000);
+    with_logging(&index, "info", |levels| {
+        tracing::debug!(target: "hal9_server::router", "hidden");
+        
+        levels.set_level("hal9_server::router", "debug").unwrap();
+        tracing::debug!(target: "hal9_server::router", "visible");
+        tracing::debug!(target: "hal9_server::api", "other module stays at info");
+        
+        assert!(levels.clear_level("hal9_server::router").unwrap());
+        tracing::debug!(target: "hal9_server::router", "hidden again");
+        
+        assert!(levels.set_level("hal9_server::router", "loud").is_err());
+        assert!(levels.set_level("bad module", "debug").is_err());
+    });
+    
+    let messages: Vec<_> = index.query(&LogQuery::default()).into_iter().map(|e| e.message).collect();
+    assert_eq!(messages, vec!["visible"]);
+}
+
+#[test]
+fn test_buffer_stays_bounded_under_flood() {
+    let index = index(100);
+    with_logging(&index, "trace", |_| {
+        let long = "x".repeat(5_000);
+        for i in 0..10_000 {
+            tracing::info!(payload = %long, "flood {} {}", i, long);
+        }
+    });
+    
+    let stats = index.stats();
+    assert_eq!(stats.entries, 100);
+    assert_eq!(stats.indexed_total, 10_000);
+    assert_eq!(stats.evicted_total, 9_900);
+    assert!(stats.avg_index_ns > 0);
+    
+    // Oldest entries went first, and oversized text is truncated
+    let entries = index.query(&LogQuery { limit: Some(1000), ..Default::default() });
+    assert_eq!(entries.len(), 100);
+    assert!(entries[0].message.starts_with("flood 9900 "));
+    assert!(entries.iter().all(|e| e.message.len() <= 2048));
+    assert!(entries.iter().all(|e| e.fields["payload"].as_str().unwrap().len() <= 256));
+}
+
+#[test]
+fn test_shrinking_capacity_evicts_immediately() {
+    let index = index(100);
+    with_logging(&index, "trace", |_| {
+        for i in 0..50 {
+            tracing::info!("entry {}", i);
+        }
+    });
+    
+    index.configure(&LogIndexConfig { enabled: true, capacity: 10, retention_hours: 24 });
+    assert_eq!(index.stats().entries, 10);
+    
+    index.configure(&LogIndexConfig { enabled: false, capacity: 10, retention_hours: 24 });
+    assert_eq!(index.stats().entries, 0);
+}
@@ -0,0 +1,64 @@
+//! MCP over HTTP: API keys, the scopes each tool needs, and memory read
+//! under the namespace policy as the caller
+
+mod common;
+
+use axum::{http::StatusCode, Router};
+use serde_json::{json, Value};
+
+use hal9_server::api::create_api_router;
+use hal9_server::dev::{self, DevOptions};
+
+use common::{request, send};
+
+/// Call `tool` as the holder of `key`, answered with the tool result
+async fn call_tool(app: &Router, key: &str, tool: &str, arguments: Value) -> Value {
+    let message = json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": "tools/call",
+        "params": {"name": tool, "arguments": arguments},
+    });
+    let reply = send(app, request("POST", "/mcp", &[("x-api-key", key)], Some(message))).await;
+    assert_eq!(reply.status, StatusCode::OK, "{}", reply.text());
+    reply.json()["result"].clone()
+}
+
+fn text(result: &Value) -> &str {
+    result["content"][0]["text"].as_str().unwrap()
+}
+
+#[tokio::test]
+async fn test_tools_run_with_the_callers_permissions() {
+    let stack = dev::boot(&DevOptions::default()).await.unwrap();
+    let app = create_api_router(stack.server.clone());
+    let key = |username: &str| stack.users.iter().find(|user| user.username == username).unwrap().api_key.clone();
+
+    let ping = json!({"jsonrpc": "2.0", "id": 1, "method": "ping"});
+    let reply = send(&app, request("POST", "/mcp", &[], Some(ping.clone()))).await;
+    assert_eq!(reply.status, StatusCode::UNAUTHORIZED);
+    let reply = send(&app, request("POST", "/mcp", &[("x-api-key", "hal9_unknown")], Some(ping))).await;
+    assert_eq!(reply.status, StatusCode::UNAUTHORIZED);
+
+    // A guest may look at the neurons but not send signals or read memory
+    let guest = key("guest");
+    let result = call_tool(&app, &guest, "list_neurons", json!({})).await;
+    assert_eq!(result["isError"], false, "{}", result);
+    let result = call_tool(&app, &guest, "submit_signal", json!({"layer": "L4", "content": "Plan it"})).await;
+    assert_eq!(result["isError"], true);
+    assert!(text(&result).contains("SendSignal"), "{}", result);
+    let result = call_tool(&app, &guest, "search_memory", json!({"query": "plan"})).await;
+    assert_eq!(result["isError"], true);
+    assert!(text(&result).contains("ViewMemory"), "{}", result);
+
+    // A developer reads shared memory, but no neuron's private namespace
+    let developer = key("developer");
+    let result = call_tool(&app, &developer, "search_memory", json!({"query": "plan"})).await;
+    assert_eq!(result["isError"], false, "{}", result);
+    let private = json!({"query": "plan", "namespace": "private:neuron-l4-strategic"});
+    let result = call_tool(&app, &developer, "search_memory", private).await;
+    assert_eq!(result["isError"], true);
+    assert!(text(&result).contains("may not read"), "{}", result);
+
+    stack.shutdown().await.unwrap();
+}
@@ -0,0 +1,219 @@
+//! Moderated memory namespaces: pending writes kept out of prompts until
+//! approved through the API or by the judge
+
+mod common;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::http::StatusCode;
+use serde_json::json;
+
+use hal9_core::config::MemoryNamespacesConfig;
+use hal9_core::memory::{
+    MemoryBuilder, MemoryNamespace, MemoryReview, MemorySearch, MemoryStore, NamespacedMemory, ReviewSummary,
+    ReviewVerdict, SqliteMemoryStore,
+};
+use hal9_core::{NeuronSignal, ServerConfig};
+use hal9_server::api::create_api_router;
+use hal9_server::chain_tracker::ChainStatus;
+use hal9_server::dev::{self, DevOptions};
+use hal9_server::memory_review::{judge_prompt, parse_verdict, JudgeReviewer};
+use hal9_server::server::HAL9Server;
+use hal9_server::MockClaude;
+
+use common::{call, request, send};
+
+fn config(dir: &tempfile::TempDir) -> ServerConfig {
+    common::mock_config(json!({
+        "server_id": "memory-moderation-test",
+        "neurons": [
+            {"id": "planner", "layer": "L4", "forward_connections": ["coder"], "backward_connections": []},
+            {"id": "coder", "layer": "L2", "forward_connections": [], "backward_connections": ["planner"]},
+        ],
+        "claude": {
+            "mock_responses": {
+                "L4": [{"trigger": "default", "response": "FORWARD_TO: coder\nCONTENT: Build it", "delay_ms": 0}],
+                "L2": [{"trigger": "default", "response": "RESULT: Built", "delay_ms": 0}],
+            },
+        },
+        "memory": {
+            "enabled": true,
+            "database_path": dir.path().join("memory.db").to_str().unwrap(),
+            "namespaces": {"overrides": {"private:planner": {"moderated": true}}},
+        },
+    }))
+}
+
+async fn run_chain(server: &HAL9Server) {
+    let signal = NeuronSignal::forward("api-client", "planner", "API", "L4", "Plan it".into());
+    let chain_id = server.submit_signal(signal).await.unwrap();
+    tokio::time::timeout(Duration::from_secs(10), async {
+        while server.get_chain_result(&chain_id).await.unwrap().status == ChainStatus::Running {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("chain did not finish");
+}
+
+fn planner_memories() -> MemorySearch {
+    MemorySearch { neuron_id: Some("planner".to_string()), limit: 100, ..Default::default() }
+}
+
+#[tokio::test]
+async fn test_pending_writes_are_hidden_until_approved() {
+    let dir = tempfile::tempdir().unwrap();
+    let server = Arc::new(HAL9Server::new(config(&dir)));
+    server.start().await.unwrap();
+    run_chain(&server).await;
+    let app = create_api_router(server.clone());
+
+    // The planner's task and result wait for review; the coder's namespace is not moderated
+    let (status, body) = call(&app, "GET", "/api/v1/memory/pending?namespace=private:planner", None).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    let pending = body["data"]["items"].as_array().unwrap().clone();
+    assert_eq!(pending.len(), 2, "{}", body);
+    assert!(pending.iter().all(|entry| entry["review"] == "pending"));
+    assert!(server.search_memory(planner_memories()).await.unwrap().is_empty());
+    let coder = MemorySearch { neuron_id: Some("coder".to_string()), ..Default::default() };
+    assert_eq!(server.search_memory(coder).await.unwrap().len(), 2);
+
+    // Approved writes reach prompts, rejected ones keep their reason
+    let (approve, reject) = (pending[0]["id"].as_str().unwrap(), pending[1]["id"].as_str().unwrap());
+    let (status, body) = call(&app, "POST", &format!("/api/v1/memory/pending/{}/approve", approve), None).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    let (status, body) = call(
+        &app,
+        "POST",
+        &format!("/api/v1/memory/pending/{}/reject", reject),
+        Some(json!({"reason": "overconfident"})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+
+    let visible = server.search_memory(planner_memories()).await.unwrap();
+    assert_eq!(visible.len(), 1);
+    assert_eq!(visible[0].id.to_string(), approve);
+    let rejected = server
+        .search_memory(MemorySearch { review: MemoryReview::Rejected, ..planner_memories() })
+        .await
+        .unwrap();
+    assert_eq!(rejected[0].review_reason.as_deref(), Some("overconfident"));
+
+    let (_, body) = call(&app, "GET", "/api/v1/memory/stats", None).await;
+    let planner = body["data"]["namespaces"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|ns| ns["namespace"] == "private:planner")
+        .unwrap()
+        .clone();
+    assert_eq!(planner["moderated"], true);
+    assert_eq!((planner["approved"].as_u64(), planner["pending"].as_u64(), planner["rejected"].as_u64()), (Some(1), Some(0), Some(1)));
+
+    // Only pending writes can be reviewed
+    let (status, _) = call(&app, "POST", &format!("/api/v1/memory/pending/{}/approve", reject), None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, _) = call(
+        &app,
+        "POST",
+        &format!("/api/v1/memory/pending/{}/reject", approve),
+        Some(json!({"reason": " "})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    server.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_reviews_need_a_moderator() {
+    let stack = dev::boot(&DevOptions::default()).await.unwrap();
+    let app = create_api_router(stack.server.clone());
+    let key = |username: &str| stack.users.iter().find(|user| user.username == username).unwrap().api_key.clone();
+    let id = uuid::Uuid::new_v4();
+    let routes = [
+        ("GET", "/api/v1/memory/pending".to_string(), None),
+        ("POST", format!("/api/v1/memory/pending/{}/approve", id), None),
+        ("POST", format!("/api/v1/memory/pending/{}/reject", id), Some(json!({"reason": "overconfident"}))),
+    ];
+
+    for (method, path, body) in &routes {
+        let reply = send(&app, request(method, path, &[], body.clone())).await;
+        assert_eq!(reply.status, StatusCode::UNAUTHORIZED, "{} {}", method, path);
+
+        // A developer reads memory but may not decide what it keeps
+        for username in ["developer", "guest"] {
+            let reply = send(&app, request(method, path, &[("x-api-key", &key(username))], body.clone())).await;
+            assert_eq!(reply.status, StatusCode::FORBIDDEN, "{} {} as {}", method, path, username);
+        }
+    }
+
+    let reply = send(&app, request("GET", "/api/v1/memory/pending", &[("x-api-key", &key("admin"))], None)).await;
+    assert_eq!(reply.status, StatusCode::OK, "{}", reply.text());
+    let approve = format!("/api/v1/memory/pending/{}/approve", id);
+    let reply = send(&app, request("POST", &approve, &[("x-api-key", &key("admin"))], None)).await;
+    assert_eq!(reply.status, StatusCode::NOT_FOUND);
+
+    stack.shutdown().await.unwrap();
+}
+
+async fn moderated_memory() -> NamespacedMemory {
+    let store = SqliteMemoryStore::in_memory().await.unwrap();
+    store.initialize().await.unwrap();
+    let mut config = MemoryNamespacesConfig::default();
+    config.layer.moderated = true;
+    config.policy.layer_write = true;
+    NamespacedMemory::new(Arc::new(store), config)
+}
+
+#[tokio::test]
+async fn test_judge_approves_consistent_and_rejects_contradicting_writes() {
+    let mut memory = moderated_memory().await;
+    let judge = MockClaude::scripted(
+        "L4",
+        vec![
+            "APPROVE".to_string(),
+            "REJECT: contradicts 'Retry flaky calls twice'".to_string(),
+            "I am not sure".to_string(),
+        ],
+    );
+    memory.set_reviewer(Arc::new(JudgeReviewer::new(Box::new(judge))));
+    let layer = MemoryNamespace::Layer("L4".to_string());
+    let lesson = |content: &str| MemoryBuilder::new("strategist".to_string(), "L4".to_string())
+        .with_content(content.to_string())
+        .build();
+
+    memory.write("strategist", "L4", &layer, lesson("Retry flaky calls twice")).await.unwrap();
+    assert_eq!(memory.review_pending().await.unwrap(), ReviewSummary { approved: 1, rejected: 0, deferred: 0 });
+
+    let wrong = memory.write("strategist", "L4", &layer, lesson("Never retry")).await.unwrap();
+    assert_eq!(memory.review_pending().await.unwrap(), ReviewSummary { approved: 0, rejected: 1, deferred: 0 });
+    let wrong = memory.store().get(wrong).await.unwrap().unwrap();
+    assert_eq!(wrong.review, MemoryReview::Rejected);
+    assert_eq!(wrong.review_reason.as_deref(), Some("contradicts 'Retry flaky calls twice'"));
+
+    // An unclear answer leaves the write for a person
+    memory.write("strategist", "L4", &layer, lesson("Cache everything")).await.unwrap();
+    assert_eq!(memory.review_pending().await.unwrap(), ReviewSummary { approved: 0, rejected: 0, deferred: 1 });
+    assert_eq!(memory.pending(Some(&layer), 10).await.unwrap().len(), 1);
+
+    let read = memory.read("strategist", "L4", &layer, MemorySearch::default()).await.unwrap();
+    assert_eq!(read.iter().map(|e| e.content.as_str()).collect::<Vec<_>>(), vec!["Retry flaky calls twice"]);
+}
+
+#[test]
+fn test_judge_prompt_and_verdicts() {
+    let entry = |content: &str| MemoryBuilder::new("strategist".to_string(), "L4".to_string())
+        .with_content(content.to_string())
+        .build();
+    let prompt = judge_prompt(&entry("Never retry"), &[entry("Retry flaky calls twice")]);
+    assert!(prompt.contains("- Retry flaky calls twice\n"));
+    assert!(prompt.contains("NEW ENTRY (from strategist, L4):\nNever retry"));
+
+    assert_eq!(parse_verdict(" approve."), ReviewVerdict::Approve);
+    assert_eq!(parse_verdict("REJECT: wrong"), ReviewVerdict::Reject("wrong".to_string()));
+    assert_eq!(parse_verdict("REJECT"), ReviewVerdict::Reject("rejected by the memory judge".to_string()));
+    assert_eq!(parse_verdict("Maybe"), ReviewVerdict::Defer);
+}
@@ -0,0 +1,274 @@
+//! Summarization of memory namespaces near their quota: clusters merged
+//! into summaries, provenance links in both directions, reads of archived
+//! entries and the daily cost cap
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use chrono::Utc;
+use parking_lot::Mutex;
+use uuid::Uuid;
+
+use hal9_core::config::{MemoryNamespacesConfig, MemorySummarizationConfig, NamespaceLimits};
+use hal9_core::memory::{
+    ColdTier, FsObjectStore, MemoryBuilder, MemoryEntry, MemoryNamespace, MemorySearch, MemoryStore,
+    MemorySummarizer, NamespacedMemory, SqliteMemoryStore, Summary,
+};
+use hal9_core::{Error, Result};
+use hal9_server::memory_summarization::SummarizationJob;
+
+/// Entries of n1's private namespace: two clusters and two loners
+const FIXTURE: [&str; 9] = [
+    "Retry the payment gateway call after a timeout with exponential backoff",
+    "Invalidate the user profile cache whenever the profile is updated",
+    "Retry the payment gateway call after a timeout, backing off exponentially",
+    "The billing report runs at midnight UTC",
+    "Retry the payment gateway call after a timeout with jittered exponential backoff",
+    "Invalidate the user profile cache whenever the user profile is updated",
+    "Retry the payment gateway call after a gateway timeout with exponential backoff",
+    "Escalate security alerts to the on-call engineer",
+    "Invalidate the user profile cache after the profile is updated",
+];
+
+/// Summarizes without a model, remembering what it was asked to merge
+#[derive(Default)]
+struct MockSummarizer {
+    calls: Mutex<Vec<Vec<Uuid>>>,
+    /// Entry deleted while the next cluster is summarized
+    delete_during_call: Mutex<Option<(Arc<SqliteMemoryStore>, Uuid)>>,
+}
+
+#[async_trait]
+impl MemorySummarizer for MockSummarizer {
+    async fn summarize(&self, entries: &[MemoryEntry]) -> Result<Summary> {
+        let content = {
+            let mut calls = self.calls.lock();
+            calls.push(entries.iter().map(|entry| entry.id).collect());
+            format!("Lesson {} of {} entries: {}", calls.len(), entries.len(), entries[0].content)
+        };
+        let delete = self.delete_during_call.lock().take();
+        if let Some((store, id)) = delete {
+            sqlx::query("DELETE FROM memories WHERE id = ?")
+                .bind(id.to_string())
+                .execute(store.pools().writer())
+                .await
+                .unwrap();
+        }
+        Ok(Summary { content, cost_usd: 0.01 })
+    }
+}
+
+struct Fixture {
+    memory: Arc<NamespacedMemory>,
+    store: Arc<SqliteMemoryStore>,
+    summarizer: Arc<MockSummarizer>,
+    ids: Vec<Uuid>,
+    _cold: tempfile::TempDir,
+}
+
+fn config() -> MemorySummarizationConfig {
+    MemorySummarizationConfig {
+        high_water: 0.8,
+        low_water: 0.5,
+        min_similarity: 0.6,
+        min_cluster_size: 3,
+        summaries_per_minute: 0,
+        ..Default::default()
+    }
+}
+
+/// n1's private namespace, limited to 10 entries, holding the first
+/// `entries` of [`FIXTURE`]
+async fn fixture(entries: usize) -> Fixture {
+    let cold = tempfile::tempdir().unwrap();
+    let store = SqliteMemoryStore::in_memory()
+        .await
+        .unwrap()
+        .with_cold_tier(Arc::new(ColdTier::new(Arc::new(FsObjectStore::new(cold.path())), 0)));
+    store.initialize().await.unwrap();
+    let store = Arc::new(store);
+
+    let namespaces = MemoryNamespacesConfig {
+        private: NamespaceLimits { ttl_secs: None, max_entries: Some(10), max_bytes: None, moderated: false },
+        ..Default::default()
+    };
+    let memory = Arc::new(NamespacedMemory::new(store.clone(), namespaces));
+
+    let own = MemoryNamespace::Private("n1".to_string());
+    let mut ids = Vec::new();
+    for (i, content) in FIXTURE.iter().take(entries).enumerate() {
+        let mut entry = MemoryBuilder::new("n1".to_string(), "L3".to_string())
+            .with_content(content.to_string())
+            .build();
+        entry.timestamp = Utc::now() - chrono::Duration::minutes(60 - i as i64);
+        ids.push(memory.write("n1", "L3", &own, entry).await.unwrap());
+    }
+    Fixture { memory, store, summarizer: Arc::new(MockSummarizer::default()), ids, _cold: cold }
+}
+
+fn job(fixture: &Fixture, config: MemorySummarizationConfig) -> SummarizationJob {
+    SummarizationJob::new(config, fixture.memory.clone(), fixture.store.clone(), fixture.summarizer.clone(), 384)
+}
+
+/// Summary and object key of an entry's row as stored
+async fn row(store: &SqliteMemoryStore, id: Uuid) -> (Option<String>, Option<String>) {
+    sqlx::query_as("SELECT archived_into, cold_key FROM memories WHERE id = ?")
+        .bind(id.to_string())
+        .fetch_one(store.pools().reader())
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_clusters_are_summarized_until_under_low_water() {
+    let fixture = fixture(9).await;
+    let report = job(&fixture, config()).run(Utc::now()).await.unwrap();
+
+    let ids = &fixture.ids;
+    assert_eq!(report.namespaces, 1);
+    assert_eq!(report.merges.len(), 2);
+    assert_eq!(report.merges[0].archived, vec![ids[0], ids[2], ids[4], ids[6]], "largest cluster first");
+    assert_eq!(report.merges[1].archived, vec![ids[1], ids[5], ids[8]]);
+    assert_eq!(*fixture.summarizer.calls.lock(), vec![report.merges[0].archived.clone(), report.merges[1].archived.clone()]);
+    assert!((report.cost_usd - 0.02).abs() < 1e-9);
+    assert!(!report.capped);
+
+    // Two loners and two summaries are left counting against the quota
+    let stats = fixture.memory.stats().await.unwrap();
+    assert_eq!((stats[0].entries, stats[0].archived), (4, 7));
+
+    // Under the high-water mark nothing more happens
+    let report = job(&fixture, config()).run(Utc::now()).await.unwrap();
+    assert_eq!((report.namespaces, report.merges.len()), (0, 0));
+}
+
+#[tokio::test]
+async fn test_namespace_under_high_water_is_left_alone() {
+    let fixture = fixture(7).await;
+    let report = job(&fixture, config()).run(Utc::now()).await.unwrap();
+
+    assert_eq!(report.namespaces, 0);
+    assert!(fixture.summarizer.calls.lock().is_empty());
+    assert_eq!(fixture.memory.stats().await.unwrap()[0].archived, 0);
+}
+
+#[tokio::test]
+async fn test_provenance_links_agree_both_ways() {
+    let fixture = fixture(9).await;
+    let report = job(&fixture, config()).run(Utc::now()).await.unwrap();
+
+    for merge in &report.merges {
+        let summary = fixture.store.get(merge.summary_id).await.unwrap().unwrap();
+        assert_eq!(summary.summary_of, merge.archived);
+        assert_eq!(summary.archived_into, None);
+        assert_eq!(summary.namespace, "private:n1");
+        assert_eq!(summary.neuron_id, "n1");
+
+        for &id in &merge.archived {
+            let (archived_into, cold_key) = row(&fixture.store, id).await;
+            assert_eq!(archived_into, Some(merge.summary_id.to_string()));
+            assert!(cold_key.is_some(), "archived entries move to the cold tier");
+        }
+        let linked: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM memories WHERE archived_into = ?")
+            .bind(merge.summary_id.to_string())
+            .fetch_one(fixture.store.pools().reader())
+            .await
+            .unwrap();
+        assert_eq!(linked as usize, merge.archived.len());
+    }
+    for id in [fixture.ids[3], fixture.ids[7]] {
+        assert_eq!(row(&fixture.store, id).await, (None, None));
+    }
+}
+
+#[tokio::test]
+async fn test_summary_is_all_or_nothing_when_an_entry_goes_away() {
+    let fixture = fixture(9).await;
+    *fixture.summarizer.delete_during_call.lock() = Some((fixture.store.clone(), fixture.ids[2]));
+    let report = job(&fixture, config()).run(Utc::now()).await.unwrap();
+
+    // The first cluster lost an entry while summarized and is skipped
+    assert_eq!(report.skipped, 1);
+    assert_eq!(report.merges.len(), 1);
+    assert_eq!(report.merges[0].archived, vec![fixture.ids[1], fixture.ids[5], fixture.ids[8]]);
+    for id in [fixture.ids[0], fixture.ids[4], fixture.ids[6]] {
+        assert_eq!(row(&fixture.store, id).await, (None, None));
+    }
+    let summaries: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM memories WHERE summary_of IS NOT NULL")
+        .fetch_one(fixture.store.pools().reader())
+        .await
+        .unwrap();
+    assert_eq!(summaries, 1);
+}
+
+#[tokio::test]
+async fn test_archived_entries_still_resolve() {
+    let fixture = fixture(9).await;
+    let report = job(&fixture, config()).run(Utc::now()).await.unwrap();
+    let summary_id = report.merges[0].summary_id;
+
+    // Read by ID, an archived entry comes back whole from the cold tier
+    let original = fixture.store.get(fixture.ids[4]).await.unwrap().unwrap();
+    assert_eq!(original.content, FIXTURE[4]);
+    assert_eq!(original.archived_into, Some(summary_id));
+
+    // Searches it matches find its summary instead, which is newer
+    let own = MemoryNamespace::Private("n1".to_string());
+    let search = MemorySearch {
+        start_time: Some(original.timestamp - chrono::Duration::seconds(10)),
+        end_time: Some(original.timestamp + chrono::Duration::seconds(10)),
+        ..Default::default()
+    };
+    let found = fixture.memory.read("n1", "L3", &own, search).await.unwrap();
+    assert_eq!(found.iter().map(|entry| entry.id).collect::<Vec<_>>(), vec![summary_id]);
+
+    let listed = fixture.memory.read("n1", "L3", &own, MemorySearch { limit: 100, ..Default::default() }).await.unwrap();
+    assert_eq!(listed.len(), 4);
+    assert!(listed.iter().all(|entry| entry.archived_into.is_none()));
+
+    // Prompts recall the summary, not the entries it was written from
+    let context = fixture.store.build_context("n1", "payment gateway").await.unwrap();
+    let recalled: Vec<Uuid> = context.similar_experiences.iter().map(|entry| entry.id).collect();
+    assert_eq!(recalled, vec![summary_id]);
+}
+
+#[tokio::test]
+async fn test_daily_cost_cap_stops_summarization() {
+    let fixture = fixture(9).await;
+    let job = job(&fixture, MemorySummarizationConfig { max_cost_per_day: 0.01, high_water: 0.5, ..config() });
+
+    let report = job.run(Utc::now()).await.unwrap();
+    assert_eq!(report.merges.len(), 1);
+    assert!(report.capped);
+
+    // Nothing more is spent today
+    let report = job.run(Utc::now()).await.unwrap();
+    assert!(report.merges.is_empty() && report.capped);
+    assert_eq!(fixture.summarizer.calls.lock().len(), 1);
+    assert!((job.status().spent_today_usd - 0.01).abs() < 1e-9);
+
+    // The next day the rest is summarized
+    let report = job.run(Utc::now() + chrono::Duration::days(1)).await.unwrap();
+    assert_eq!(report.merges.len(), 1);
+}
+
+/// Fails every summary
+struct FailingSummarizer;
+
+#[async_trait]
+impl MemorySummarizer for FailingSummarizer {
+    async fn summarize(&self, _entries: &[MemoryEntry]) -> Result<Summary> {
+        Err(Error::ClaudeApi("overloaded".to_string()))
+    }
+}
+
+#[tokio::test]
+async fn test_failed_summaries_leave_entries_alone() {
+    let fixture = fixture(9).await;
+    let job = SummarizationJob::new(config(), fixture.memory.clone(), fixture.store.clone(), Arc::new(FailingSummarizer), 384);
+    let report = job.run(Utc::now()).await.unwrap();
+
+    assert_eq!((report.failed, report.merges.len()), (2, 0));
+    let stats = fixture.memory.stats().await.unwrap();
+    assert_eq!((stats[0].entries, stats[0].archived), (9, 0));
+}
@@ -0,0 +1,265 @@
+//! Cold tiering of memory: transparent reads of cold entries, promotion of
+//! entries read often, and moves that never lose or duplicate an entry
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use chrono::Utc;
+use parking_lot::Mutex;
+use uuid::Uuid;
+
+use hal9_core::config::MemoryTieringConfig;
+use hal9_core::memory::{
+    ColdTier, FsObjectStore, MemoryBuilder, MemoryEntry, MemorySearch, MemoryStore, ObjectStore, SqliteMemoryStore,
+};
+use hal9_core::sqlite::SqlitePools;
+use hal9_core::{Error, Result};
+use hal9_server::memory_tiering::TieringJob;
+
+/// A filesystem store whose calls fail on demand. A failing put writes the
+/// object first, like a process that dies right after the write.
+struct FaultyStore {
+    inner: FsObjectStore,
+    fail_puts: AtomicBool,
+    fail_deletes: AtomicBool,
+    /// Content written over an entry's row while the next object is written
+    change_during_put: Mutex<Option<(SqlitePools, Uuid, String)>>,
+}
+
+impl FaultyStore {
+    fn new(dir: &tempfile::TempDir) -> Arc<Self> {
+        Arc::new(Self {
+            inner: FsObjectStore::new(dir.path()),
+            fail_puts: AtomicBool::new(false),
+            fail_deletes: AtomicBool::new(false),
+            change_during_put: Mutex::new(None),
+        })
+    }
+}
+
+#[async_trait]
+impl ObjectStore for FaultyStore {
+    fn kind(&self) -> &'static str {
+        "faulty"
+    }
+
+    async fn put(&self, key: &str, bytes: Vec<u8>) -> Result<()> {
+        self.inner.put(key, bytes).await?;
+        let change = self.change_during_put.lock().take();
+        if let Some((pools, id, content)) = change {
+            sqlx::query("UPDATE memories SET content = ? WHERE id = ?")
+                .bind(content)
+                .bind(id.to_string())
+                .execute(pools.writer())
+                .await
+                .unwrap();
+        }
+        if self.fail_puts.load(Ordering::SeqCst) {
+            return Err(Error::Storage("crashed after the write".to_string()));
+        }
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        self.inner.get(key).await
+    }
+
+    async fn delete(&self, key: &str) -> Result<()> {
+        if self.fail_deletes.load(Ordering::SeqCst) {
+            return Err(Error::Storage("delete refused".to_string()));
+        }
+        self.inner.delete(key).await
+    }
+
+    async fn list(&self, prefix: &str) -> Result<Vec<String>> {
+        self.inner.list(prefix).await
+    }
+}
+
+async fn store(objects: Arc<dyn ObjectStore>, promote_after_reads: u32) -> Arc<SqliteMemoryStore> {
+    let store = SqliteMemoryStore::in_memory()
+        .await
+        .unwrap()
+        .with_cold_tier(Arc::new(ColdTier::new(objects, promote_after_reads)));
+    store.initialize().await.unwrap();
+    Arc::new(store)
+}
+
+fn job(store: &Arc<SqliteMemoryStore>) -> TieringJob {
+    let config = MemoryTieringConfig { min_importance: 0.0, moves_per_second: 0, ..Default::default() };
+    TieringJob::new(config, store.clone())
+}
+
+/// An entry last read `idle_days` ago
+fn entry(content: &str, idle_days: i64) -> MemoryEntry {
+    let mut entry = MemoryBuilder::new("coder".to_string(), "L2".to_string())
+        .with_content(content.to_string())
+        .with_importance(0.5)
+        .build();
+    entry.last_accessed = Utc::now() - chrono::Duration::days(idle_days);
+    entry
+}
+
+/// Content and object key of an entry's row as stored
+async fn row(store: &SqliteMemoryStore, id: Uuid) -> (String, Option<String>) {
+    sqlx::query_as("SELECT content, cold_key FROM memories WHERE id = ?")
+        .bind(id.to_string())
+        .fetch_one(store.pools().reader())
+        .await
+        .unwrap()
+}
+
+/// Assert `id` is in exactly one tier holding `content`: the row when hot,
+/// else the one object it refers to. With `settled`, no other object of the
+/// entry may be left either.
+async fn assert_one_place(store: &SqliteMemoryStore, objects: &dyn ObjectStore, id: Uuid, content: &str, settled: bool) {
+    let (stored, key) = row(store, id).await;
+    let copies = objects.list(&format!("memories/{}/", id)).await.unwrap();
+    match &key {
+        None => assert_eq!(stored, content, "a hot entry keeps its content in the row"),
+        Some(key) => {
+            assert_eq!(stored, "", "a cold entry's row is a stub");
+            let object = objects.get(key).await.unwrap().expect("a stub refers to an object");
+            assert_eq!(String::from_utf8(object).unwrap(), content);
+        }
+    }
+    if settled {
+        assert_eq!(copies, key.into_iter().collect::<Vec<_>>(), "no stray copy of {}", id);
+    }
+    assert_eq!(store.get(id).await.unwrap().unwrap().content, content);
+}
+
+#[tokio::test]
+async fn test_cold_entries_read_transparently() {
+    let dir = tempfile::tempdir().unwrap();
+    let objects: Arc<dyn ObjectStore> = Arc::new(FsObjectStore::new(dir.path()));
+    let store = store(objects.clone(), 0).await;
+    let idle = entry("The deploy script needs --frozen-lockfile", 30);
+    let recent = entry("Staging runs on port 8081", 1);
+    let namespace = idle.namespace.clone();
+    store.store(idle.clone()).await.unwrap();
+    store.store(recent.clone()).await.unwrap();
+    let before = store.namespace_usage(Some(&namespace)).await.unwrap();
+
+    let report = job(&store).run(Utc::now()).await.unwrap();
+    assert_eq!((report.moved, report.skipped, report.failed), (1, 0, 0));
+
+    // Only a stub is left in the database
+    let (content, key) = row(&store, idle.id).await;
+    assert_eq!(content, "");
+    assert!(key.is_some());
+    assert_eq!(row(&store, recent.id).await, (recent.content.clone(), None));
+    let usage = store.tier_usage().await.unwrap();
+    assert_eq!((usage.hot.entries, usage.cold.entries), (1, 1));
+    assert_eq!(usage.cold.bytes, idle.content.len() as u64);
+    // Quotas still count what cold entries hold
+    assert_eq!(store.namespace_usage(Some(&namespace)).await.unwrap()[0].bytes, before[0].bytes);
+
+    // Reads fetch the content from the cold tier, and are counted
+    let read = store.get(idle.id).await.unwrap().unwrap();
+    assert_eq!(read.content, idle.content);
+    assert_eq!(read.neuron_id, "coder");
+    let found = store
+        .search(MemorySearch { neuron_id: Some("coder".to_string()), ..Default::default() })
+        .await
+        .unwrap();
+    assert!(found.iter().any(|entry| entry.id == idle.id && entry.content == idle.content));
+    let stats = store.cold_tier().unwrap().stats();
+    assert_eq!((stats.backend.as_str(), stats.reads, stats.promotions), ("filesystem", 2, 0));
+
+    // A dump reads cold content without counting towards promotion
+    let exported = store.export(None, None).await.unwrap();
+    assert!(exported.iter().any(|entry| entry.content == idle.content));
+
+    // Writes go hot, even over a cold entry
+    let mut rewritten = idle.clone();
+    rewritten.content = "The deploy script needs --locked".to_string();
+    store.replace(rewritten.clone()).await.unwrap();
+    assert_eq!(row(&store, idle.id).await, (rewritten.content.clone(), None));
+    let report = job(&store).run(Utc::now()).await.unwrap();
+    assert_eq!(report.orphans_deleted, 1, "the replaced entry's object is deleted");
+    assert_eq!(report.moved, 1, "and the still idle entry moves again");
+    assert_one_place(&store, objects.as_ref(), idle.id, &rewritten.content, true).await;
+}
+
+#[tokio::test]
+async fn test_frequently_read_cold_entry_is_promoted() {
+    let dir = tempfile::tempdir().unwrap();
+    let objects: Arc<dyn ObjectStore> = Arc::new(FsObjectStore::new(dir.path()));
+    let store = store(objects.clone(), 3).await;
+    let idle = entry("Use the read replica for reports", 30);
+    store.store(idle.clone()).await.unwrap();
+    assert!(store.demote(idle.id).await.unwrap());
+
+    for _ in 0..2 {
+        assert_eq!(store.get(idle.id).await.unwrap().unwrap().content, idle.content);
+        assert!(row(&store, idle.id).await.1.is_some(), "stays cold below the threshold");
+    }
+    assert_eq!(store.get(idle.id).await.unwrap().unwrap().content, idle.content);
+
+    // The third read moves it back and deletes the object
+    assert_eq!(row(&store, idle.id).await, (idle.content.clone(), None));
+    assert!(objects.list("memories/").await.unwrap().is_empty());
+    let stats = store.cold_tier().unwrap().stats();
+    assert_eq!((stats.reads, stats.promotions), (3, 1));
+
+    // Hot reads no longer touch the cold tier, and the promoted entry
+    // counts as just read rather than moving straight back
+    store.get(idle.id).await.unwrap();
+    assert_eq!(store.cold_tier().unwrap().stats().reads, 3);
+    assert_eq!(job(&store).run(Utc::now()).await.unwrap().moved, 0);
+}
+
+#[tokio::test]
+async fn test_moves_never_lose_or_duplicate_an_entry() {
+    let dir = tempfile::tempdir().unwrap();
+    let objects = FaultyStore::new(&dir);
+    let store = store(objects.clone(), 2).await;
+    let idle = entry("Retry flaky uploads twice", 30);
+    store.store(idle.clone()).await.unwrap();
+    assert_one_place(&store, objects.as_ref(), idle.id, &idle.content, true).await;
+
+    // Crash after the object is written, before the row refers to it: the
+    // entry stays hot and the stray object goes on the next run
+    objects.fail_puts.store(true, Ordering::SeqCst);
+    assert!(store.demote(idle.id).await.is_err());
+    assert_one_place(&store, objects.as_ref(), idle.id, &idle.content, false).await;
+    assert_eq!(objects.list("memories/").await.unwrap().len(), 1);
+    let report = job(&store).run(Utc::now()).await.unwrap();
+    assert_eq!((report.orphans_deleted, report.moved, report.failed), (1, 0, 1));
+    assert_one_place(&store, objects.as_ref(), idle.id, &idle.content, false).await;
+    objects.fail_puts.store(false, Ordering::SeqCst);
+    store.reconcile_cold().await.unwrap();
+    assert_one_place(&store, objects.as_ref(), idle.id, &idle.content, true).await;
+
+    // A move completes; the check reads the entry once while cold
+    assert!(store.demote(idle.id).await.unwrap());
+    assert_one_place(&store, objects.as_ref(), idle.id, &idle.content, true).await;
+    assert!(!store.demote(idle.id).await.unwrap(), "a cold entry is not moved twice");
+
+    // Promotion commits before the object is deleted; a failed delete
+    // leaves a stray copy, never a stub without one
+    objects.fail_deletes.store(true, Ordering::SeqCst);
+    assert_eq!(store.get(idle.id).await.unwrap().unwrap().content, idle.content);
+    assert_eq!(row(&store, idle.id).await.1, None);
+    assert_one_place(&store, objects.as_ref(), idle.id, &idle.content, false).await;
+    assert!(store.reconcile_cold().await.is_err());
+    objects.fail_deletes.store(false, Ordering::SeqCst);
+    assert_eq!(store.reconcile_cold().await.unwrap(), 1);
+    assert_one_place(&store, objects.as_ref(), idle.id, &idle.content, true).await;
+
+    // An entry changed while its object is written stays hot as changed,
+    // and the object is dropped
+    let changed = "Retry flaky uploads three times";
+    *objects.change_during_put.lock() = Some((store.pools().clone(), idle.id, changed.to_string()));
+    assert!(!store.demote(idle.id).await.unwrap());
+    assert_one_place(&store, objects.as_ref(), idle.id, changed, true).await;
+
+    // A deleted cold entry leaves nothing behind
+    assert!(store.demote(idle.id).await.unwrap());
+    store.cleanup(Utc::now() + chrono::Duration::days(1), 1.0).await.unwrap();
+    assert!(store.get(idle.id).await.unwrap().is_none());
+    assert_eq!(store.reconcile_cold().await.unwrap(), 1);
+    assert!(objects.list("memories/").await.unwrap().is_empty());
+}
@@ -0,0 +1,310 @@
+//! Memory export and import between environments
+
+mod common;
+
+use axum::http::StatusCode;
+use chrono::{Duration, Utc};
+use tempfile::TempDir;
+
+use hal9_core::memory::{EmbeddingGenerator, MemoryBuilder, MemoryEntry, MemoryStore, MemoryType, SqliteMemoryStore};
+use hal9_core::metadata_schema::keys;
+use hal9_core::Error;
+use hal9_server::api::create_api_router;
+use hal9_server::dev::{self, DevOptions};
+use hal9_server::memory_manager::{
+    export_dump, import_dump, parse_dump, ConflictPolicy, DumpScope, ImportAction, ImportOptions, ImportReport,
+    DUMP_VERSION,
+};
+
+use common::{request, send};
+
+struct Env {
+    store: SqliteMemoryStore,
+    _dir: TempDir,
+}
+
+impl Env {
+    async fn new() -> Self {
+        let dir = TempDir::new().unwrap();
+        let store = SqliteMemoryStore::new(dir.path().join("memory.db").to_str().unwrap()).await.unwrap();
+        store.initialize().await.unwrap();
+        Self { store, _dir: dir }
+    }
+
+    async fn dump(&self, namespace: Option<&str>, neuron_id: Option<&str>) -> String {
+        let lines = export_dump(&self.store, namespace, neuron_id, &DumpScope::All, &EmbeddingGenerator::new(8)).await.unwrap();
+        lines.join("\n") + "\n"
+    }
+
+    /// Import `dump` with every organization in scope
+    async fn import(&self, dump: &str, options: &ImportOptions) -> hal9_core::Result<ImportReport> {
+        self.import_scoped(dump, options, &DumpScope::All).await
+    }
+
+    async fn import_scoped(
+        &self,
+        dump: &str,
+        options: &ImportOptions,
+        scope: &DumpScope,
+    ) -> hal9_core::Result<ImportReport> {
+        import_dump(&self.store, dump, options, scope, &EmbeddingGenerator::new(8)).await
+    }
+
+    async fn content(&self, entry: &MemoryEntry) -> String {
+        self.store.get(entry.id).await.unwrap().unwrap().content
+    }
+}
+
+/// A learned correction, created `age_hours` ago; timestamps are whole
+/// seconds as the store keeps them
+fn correction(content: &str, age_hours: i64) -> MemoryEntry {
+    let mut entry = MemoryBuilder::new("neuron-l2".to_string(), "L2".to_string())
+        .with_type(MemoryType::Learning)
+        .with_content(content.to_string())
+        .with_metadata(serde_json::json!({"source": "staging-review"}))
+        .build();
+    let created = Utc::now() - Duration::hours(age_hours);
+    entry.timestamp = chrono::DateTime::from_timestamp(created.timestamp(), 0).unwrap();
+    entry.last_accessed = entry.timestamp;
+    entry
+}
+
+fn options(policy: ConflictPolicy, dry_run: bool) -> ImportOptions {
+    ImportOptions { policy, dry_run, recompute_embeddings: false }
+}
+
+fn action_of(report: &ImportReport, entry: &MemoryEntry) -> ImportAction {
+    report.changes.iter().find(|change| change.key == entry.id).unwrap().action
+}
+
+#[tokio::test]
+async fn test_round_trip_preserves_entries() {
+    let staging = Env::new().await;
+    let mut hint = correction("Prefer iterators over index loops", 2);
+    hint.embedding = Some(vec![0.5, -0.25, 0.0, 1.0]);
+    hint.importance = 0.9;
+    hint.access_count = 7;
+    hint.expires_at = Some(chrono::DateTime::from_timestamp((Utc::now() + Duration::days(30)).timestamp(), 0).unwrap());
+    let plain = correction("Validate JSON before forwarding", 1);
+    let mut other = MemoryBuilder::new("neuron-l3".to_string(), "L3".to_string())
+        .with_content("Unrelated design note".to_string())
+        .build();
+    other.timestamp = chrono::DateTime::from_timestamp(other.timestamp.timestamp(), 0).unwrap();
+    for entry in [&hint, &plain, &other] {
+        staging.store.store(entry.clone()).await.unwrap();
+    }
+
+    let dump = staging.dump(Some("private:neuron-l2"), Some("neuron-l2")).await;
+    let (header, entries) = parse_dump(&dump).unwrap();
+    assert_eq!(header.version, DUMP_VERSION);
+    assert_eq!(header.entries, 2);
+    assert_eq!(header.embedding_provider, "hal9-ngram-8");
+    assert_eq!(entries.iter().map(|e| e.key).collect::<Vec<_>>(), vec![hint.id, plain.id]);
+    assert!(entries[0].has_embedding);
+    assert!(!entries[1].has_embedding);
+
+    let production = Env::new().await;
+    let report = production.import(&dump, &options(ConflictPolicy::Skip, false)).await.unwrap();
+    assert_eq!(report.created, 2);
+
+    // Exporting production gives back the same entries
+    let (_, reexported) = parse_dump(&production.dump(None, None).await).unwrap();
+    assert_eq!(reexported, entries);
+    let imported = production.store.get(hint.id).await.unwrap().unwrap();
+    assert_eq!(imported.embedding, hint.embedding);
+    assert_eq!((imported.access_count, imported.expires_at), (7, hint.expires_at));
+}
+
+#[tokio::test]
+async fn test_conflict_policies() {
+    let staging = Env::new().await;
+    let newer = correction("Retry with a smaller batch (revised)", 1);
+    let older = correction("Cite sources (revised)", 48);
+    for entry in [&newer, &older] {
+        staging.store.store(entry.clone()).await.unwrap();
+    }
+    let dump = staging.dump(None, None).await;
+
+    for policy in [ConflictPolicy::Skip, ConflictPolicy::Overwrite, ConflictPolicy::MergeNewer] {
+        // Production holds the same keys, written a day ago
+        let production = Env::new().await;
+        for entry in [&newer, &older] {
+            let mut stale = correction(&entry.content.replace(" (revised)", ""), 24);
+            stale.id = entry.id;
+            production.store.store(stale).await.unwrap();
+        }
+
+        let report = production.import(&dump, &options(policy, false)).await.unwrap();
+
+        let (newer_content, older_content) = (production.content(&newer).await, production.content(&older).await);
+        match policy {
+            ConflictPolicy::Skip => {
+                assert_eq!((report.skipped, report.overwritten), (2, 0));
+                assert_eq!(newer_content, "Retry with a smaller batch");
+                assert_eq!(older_content, "Cite sources");
+            }
+            ConflictPolicy::Overwrite => {
+                assert_eq!((report.skipped, report.overwritten), (0, 2));
+                assert_eq!(newer_content, newer.content);
+                assert_eq!(older_content, older.content);
+            }
+            ConflictPolicy::MergeNewer => {
+                assert_eq!((report.skipped, report.overwritten), (1, 1));
+                assert_eq!(newer_content, newer.content);
+                assert_eq!(older_content, "Cite sources");
+            }
+        }
+
+        // Replacing leaves one row per key
+        let (_, entries) = parse_dump(&production.dump(None, None).await).unwrap();
+        assert_eq!(entries.len(), 2, "{:?}", policy);
+    }
+}
+
+#[tokio::test]
+async fn test_dry_run_reports_without_writing() {
+    let staging = Env::new().await;
+    let new = correction("Summaries under 200 words", 1);
+    let changed = correction("Escalate ambiguous specs (revised)", 1);
+    let same = correction("Use snake_case in Python", 3);
+    for entry in [&new, &changed, &same] {
+        staging.store.store(entry.clone()).await.unwrap();
+    }
+    let dump = staging.dump(None, None).await;
+
+    let production = Env::new().await;
+    let mut stale = correction("Escalate ambiguous specs", 24);
+    stale.id = changed.id;
+    production.store.store(stale).await.unwrap();
+    // Read more often in production, but the same memory
+    let mut read = same.clone();
+    read.access_count = 40;
+    production.store.store(read).await.unwrap();
+    let before = production.dump(None, None).await;
+
+    let dry = production.import(&dump, &options(ConflictPolicy::Overwrite, true)).await.unwrap();
+    assert!(dry.dry_run);
+    assert_eq!((dry.created, dry.overwritten, dry.skipped, dry.unchanged), (1, 1, 0, 1));
+    assert_eq!(action_of(&dry, &new), ImportAction::Create);
+    assert_eq!(action_of(&dry, &changed), ImportAction::Overwrite);
+    assert_eq!(action_of(&dry, &same), ImportAction::Unchanged);
+    assert_eq!(parse_dump(&production.dump(None, None).await).unwrap().1, parse_dump(&before).unwrap().1);
+
+    // The real import does exactly what the dry run reported
+    let applied = production.import(&dump, &options(ConflictPolicy::Overwrite, false)).await.unwrap();
+    assert_eq!(applied.changes, dry.changes);
+    assert_eq!(production.content(&new).await, new.content);
+    assert_eq!(production.content(&changed).await, changed.content);
+    assert_eq!(production.store.get(same.id).await.unwrap().unwrap().access_count, 40);
+}
+
+#[tokio::test]
+async fn test_embeddings_recomputed_for_other_provider() {
+    let staging = Env::new().await;
+    let mut hint = correction("Prefer small pure functions", 1);
+    hint.embedding = Some(vec![1.0; 8]);
+    staging.store.store(hint.clone()).await.unwrap();
+    let dump = staging.dump(None, None).await;
+
+    let production = Env::new().await;
+    let embeddings = EmbeddingGenerator::new(16);
+    let options = ImportOptions { recompute_embeddings: true, ..options(ConflictPolicy::Skip, false) };
+    let report = import_dump(&production.store, &dump, &options, &DumpScope::All, &embeddings).await.unwrap();
+
+    assert_eq!(report.source_provider, "hal9-ngram-8");
+    assert_eq!(report.embeddings_recomputed, 1);
+    let imported = production.store.get(hint.id).await.unwrap().unwrap();
+    assert_eq!(imported.embedding, Some(embeddings.generate(&hint.content).await.unwrap()));
+}
+
+#[tokio::test]
+async fn test_malformed_dump_changes_nothing() {
+    let production = Env::new().await;
+    let staging = Env::new().await;
+    staging.store.store(correction("Keep logs structured", 1)).await.unwrap();
+    let dump = staging.dump(None, None).await + "{\"namespace\": \"global\"}\n";
+
+    let err = production.import(&dump, &options(ConflictPolicy::Skip, false)).await.unwrap_err();
+    assert!(err.to_string().contains("Line 3 is not a memory entry"), "{}", err);
+    assert!(parse_dump(&production.dump(None, None).await).unwrap().1.is_empty());
+
+    assert!(parse_dump("").is_err());
+    assert!(parse_dump("{\"format\": \"csv\"}").is_err());
+}
+
+fn in_org(mut entry: MemoryEntry, org_id: &str) -> MemoryEntry {
+    entry.metadata[keys::auth::ORG_ID] = serde_json::json!(org_id);
+    entry
+}
+
+#[tokio::test]
+async fn test_dumps_are_confined_to_an_organization() {
+    let acme_scope = DumpScope::Org(Some("acme".to_string()));
+    let staging = Env::new().await;
+    let acme = in_org(correction("Acme wraps lines at 100", 2), "acme");
+    let globex = in_org(correction("Globex wraps lines at 80", 1), "globex");
+    let unowned = correction("Validate JSON before forwarding", 1);
+    for entry in [&acme, &globex, &unowned] {
+        staging.store.store(entry.clone()).await.unwrap();
+    }
+
+    let lines = export_dump(&staging.store, None, None, &acme_scope, &EmbeddingGenerator::new(8))
+        .await
+        .unwrap();
+    let (header, entries) = parse_dump(&lines.join("\n")).unwrap();
+    assert_eq!(header.entries, 1);
+    assert_eq!(entries.iter().map(|e| e.key).collect::<Vec<_>>(), vec![acme.id]);
+
+    // A dump with another organization's entries changes nothing
+    let production = Env::new().await;
+    let everything = staging.dump(None, None).await;
+    let overwrite = options(ConflictPolicy::Overwrite, false);
+    let err = production.import_scoped(&everything, &overwrite, &acme_scope).await.unwrap_err();
+    assert!(matches!(err, Error::PermissionDenied(_)), "{}", err);
+    assert!(production.store.get(acme.id).await.unwrap().is_none());
+
+    // Nor does one claiming the key of another organization's stored entry
+    production.store.store(globex.clone()).await.unwrap();
+    let mut claimed = in_org(correction("Globex wraps lines at 120", 0), "acme");
+    claimed.id = globex.id;
+    let source = Env::new().await;
+    source.store.store(claimed).await.unwrap();
+    let err = production.import_scoped(&source.dump(None, None).await, &overwrite, &acme_scope).await.unwrap_err();
+    assert!(matches!(err, Error::PermissionDenied(_)), "{}", err);
+    assert_eq!(production.content(&globex).await, globex.content);
+
+    // The organization's own dump goes in
+    let own = lines.join("\n") + "\n";
+    let report = production.import_scoped(&own, &options(ConflictPolicy::Skip, false), &acme_scope).await.unwrap();
+    assert_eq!(report.created, 1);
+    assert_eq!(production.content(&acme).await, acme.content);
+}
+
+#[tokio::test]
+async fn test_dump_routes_need_memory_permissions() {
+    let stack = dev::boot(&DevOptions::default()).await.unwrap();
+    let app = create_api_router(stack.server.clone());
+    let key = |username: &str| stack.users.iter().find(|user| user.username == username).unwrap().api_key.clone();
+
+    let reply = send(&app, request("GET", "/api/v1/memory/export", &[], None)).await;
+    assert_eq!(reply.status, StatusCode::UNAUTHORIZED);
+    let reply = send(&app, request("POST", "/api/v1/memory/import", &[], None)).await;
+    assert_eq!(reply.status, StatusCode::UNAUTHORIZED);
+
+    // A guest may not read memory; a developer reads it but may not write it
+    let reply = send(&app, request("GET", "/api/v1/memory/export", &[("x-api-key", &key("guest"))], None)).await;
+    assert_eq!(reply.status, StatusCode::FORBIDDEN);
+    let reply = send(&app, request("GET", "/api/v1/memory/export", &[("x-api-key", &key("developer"))], None)).await;
+    assert_eq!(reply.status, StatusCode::OK, "{}", reply.text());
+    let dump = reply.text();
+    let mut import = request("POST", "/api/v1/memory/import", &[("x-api-key", &key("developer"))], None);
+    *import.body_mut() = dump.clone().into();
+    assert_eq!(send(&app, import).await.status, StatusCode::FORBIDDEN);
+
+    let mut import = request("POST", "/api/v1/memory/import", &[("x-api-key", &key("admin"))], None);
+    *import.body_mut() = dump.into();
+    let reply = send(&app, import).await;
+    assert_eq!(reply.status, StatusCode::OK, "{}", reply.text());
+
+    stack.shutdown().await.unwrap();
+}
@@ -0,0 +1,210 @@
+//! Startup migrations: upgrading databases left at each earlier schema
+//! version, replicas migrating together, and refusing databases this
+//! release cannot run against
+
+mod common;
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde_json::json;
+use sqlx::migrate::Migrator;
+use sqlx::sqlite::SqlitePool;
+
+use hal9_core::config::DatabaseConfig;
+use hal9_core::ServerConfig;
+use hal9_server::database::DatabasePool;
+use hal9_server::database_migrations::{self, SchemaError, SchemaState, SQLITE_MIGRATIONS};
+use hal9_server::server::HAL9Server;
+
+const LOCK_TIMEOUT: Duration = Duration::from_secs(30);
+
+fn migrations_dir(database: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("migrations").join(database)
+}
+
+/// A SQLite database left at `version` by an earlier release, holding a
+/// user and a neuron
+async fn fixture(dir: &Path, version: i64) -> String {
+    let earlier = dir.join(format!("migrations-{}", version));
+    std::fs::create_dir(&earlier).unwrap();
+    for migration in SQLITE_MIGRATIONS.iter().filter(|m| m.version <= version) {
+        let name = format!("{:03}_{}.sql", migration.version, migration.description.replace(' ', "_"));
+        std::fs::copy(migrations_dir("sqlite").join(&name), earlier.join(&name)).unwrap();
+    }
+
+    let url = format!("sqlite:{}?mode=rwc", dir.join(format!("v{}.db", version)).display());
+    let pool = SqlitePool::connect(&url).await.unwrap();
+    Migrator::new(earlier.as_path()).await.unwrap().run(&pool).await.unwrap();
+    sqlx::query("INSERT INTO users (id, username, password_hash) VALUES ('u1', 'ada', 'hash')")
+        .execute(&pool)
+        .await
+        .unwrap();
+    sqlx::query("INSERT INTO neurons (id, layer, system_prompt) VALUES ('planner', 'L4', 'Plan')")
+        .execute(&pool)
+        .await
+        .unwrap();
+    pool.close().await;
+    url
+}
+
+fn database(url: &str, migrate_on_start: bool) -> DatabaseConfig {
+    DatabaseConfig { url: Some(url.to_string()), migrate_on_start, lock_timeout_secs: 30 }
+}
+
+async fn connect(url: &str) -> DatabasePool {
+    database_migrations::connect(&database(url, true)).await.unwrap().unwrap()
+}
+
+fn server_config(url: &str, migrate_on_start: bool) -> ServerConfig {
+    common::mock_config(json!({
+        "server_id": "migration-test",
+        "neurons": [
+            {"id": "planner", "layer": "L4", "forward_connections": [], "backward_connections": []},
+        ],
+        "memory": {"enabled": false},
+        "database": {"url": url, "migrate_on_start": migrate_on_start},
+    }))
+}
+
+#[tokio::test]
+async fn test_upgrades_every_historical_version() {
+    let dir = tempfile::tempdir().unwrap();
+    let expected = SQLITE_MIGRATIONS.iter().map(|m| m.version).max().unwrap();
+
+    for version in 1..expected {
+        let url = fixture(dir.path(), version).await;
+        let pool = connect(&url).await;
+        let before = database_migrations::status(&pool).await.unwrap();
+        assert_eq!(before.state, SchemaState::Behind);
+        assert_eq!((before.current, before.expected), (Some(version), expected));
+        assert_eq!(before.pending.first().map(|m| m.version), Some(version + 1));
+
+        let report = database_migrations::migrate(&pool, LOCK_TIMEOUT).await.unwrap();
+        assert_eq!((report.from, report.to), (Some(version), Some(expected)));
+        assert_eq!(report.applied.len() as i64, expected - version, "from version {}", version);
+
+        let after = database_migrations::status(&pool).await.unwrap();
+        assert_eq!(after.state, SchemaState::Current, "from version {}", version);
+        assert!(after.check().is_ok());
+
+        // Rows written under the old schema survive the upgrade
+        let sqlite = pool.as_sqlite_pool().unwrap();
+        let user: (String, Option<String>) = sqlx::query_as("SELECT username, email FROM users WHERE id = 'u1'")
+            .fetch_one(sqlite)
+            .await
+            .unwrap();
+        assert_eq!(user, ("ada".to_string(), None));
+        let neurons: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM neurons").fetch_one(sqlite).await.unwrap();
+        assert_eq!(neurons, 1);
+
+        // A second run has nothing to do
+        let again = database_migrations::migrate(&pool, LOCK_TIMEOUT).await.unwrap();
+        assert!(again.applied.is_empty());
+        pool.close().await;
+    }
+}
+
+#[tokio::test]
+async fn test_fresh_database_and_concurrent_replicas() {
+    let dir = tempfile::tempdir().unwrap();
+    let url = format!("sqlite:{}?mode=rwc", dir.path().join("fresh.db").display());
+    let replicas: Vec<DatabasePool> = vec![connect(&url).await, connect(&url).await, connect(&url).await];
+
+    let fresh = database_migrations::status(&replicas[0]).await.unwrap();
+    assert_eq!((fresh.state, fresh.current), (SchemaState::Behind, None));
+
+    // Whichever replica gets the lock migrates; the others find nothing to do
+    let reports = futures::future::join_all(
+        replicas.iter().map(|pool| database_migrations::migrate(pool, LOCK_TIMEOUT)),
+    )
+    .await;
+    let applied: usize = reports.into_iter().map(|report| report.unwrap().applied.len()).sum();
+    assert_eq!(applied, SQLITE_MIGRATIONS.iter().count());
+
+    let sqlite = replicas[0].as_sqlite_pool().unwrap();
+    let rows: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM _sqlx_migrations").fetch_one(sqlite).await.unwrap();
+    assert_eq!(rows as usize, SQLITE_MIGRATIONS.iter().count());
+    for pool in replicas {
+        pool.close().await;
+    }
+}
+
+#[tokio::test]
+async fn test_refuses_database_from_newer_release() {
+    let dir = tempfile::tempdir().unwrap();
+    let url = fixture(dir.path(), 1).await;
+    let pool = connect(&url).await;
+    database_migrations::migrate(&pool, LOCK_TIMEOUT).await.unwrap();
+
+    // What a newer release would have recorded after its own migration
+    let sqlite = pool.as_sqlite_pool().unwrap();
+    sqlx::query(
+        "INSERT INTO _sqlx_migrations (version, description, success, checksum, execution_time) \
+         VALUES (99, 'from the future', TRUE, X'00', 0)",
+    )
+    .execute(sqlite)
+    .await
+    .unwrap();
+
+    let status = database_migrations::status(&pool).await.unwrap();
+    assert_eq!((status.state, status.unknown.as_slice()), (SchemaState::Ahead, &[99][..]));
+    let err = database_migrations::migrate(&pool, LOCK_TIMEOUT).await.unwrap_err();
+    assert!(matches!(err, SchemaError::TooNew { current: 99, .. }), "{}", err);
+    assert!(err.to_string().contains("Downgrading is not supported"), "{}", err);
+    pool.close().await;
+
+    let server = Arc::new(HAL9Server::new(server_config(&url, true)));
+    let err = server.start().await.unwrap_err();
+    assert!(err.to_string().contains("migrated by a newer release"), "{}", err);
+}
+
+#[tokio::test]
+async fn test_startup_migration_can_be_left_to_an_init_container() {
+    let dir = tempfile::tempdir().unwrap();
+    let url = fixture(dir.path(), 2).await;
+
+    // Without migrate_on_start a database behind this release stops the server...
+    let server = Arc::new(HAL9Server::new(server_config(&url, false)));
+    let err = server.start().await.unwrap_err();
+    assert!(err.to_string().contains("--migrate-only"), "{}", err);
+    let pool = connect(&url).await;
+    assert_eq!(database_migrations::status(&pool).await.unwrap().current, Some(2));
+
+    // ...until something migrates it
+    database_migrations::migrate(&pool, LOCK_TIMEOUT).await.unwrap();
+    pool.close().await;
+    let server = Arc::new(HAL9Server::new(server_config(&url, false)));
+    server.start().await.unwrap();
+    server.shutdown().await.unwrap();
+
+    // With it the server migrates the database itself
+    let url = fixture(dir.path(), 1).await;
+    let server = Arc::new(HAL9Server::new(server_config(&url, true)));
+    server.start().await.unwrap();
+    server.shutdown().await.unwrap();
+    let pool = connect(&url).await;
+    assert_eq!(database_migrations::status(&pool).await.unwrap().state, SchemaState::Current);
+    pool.close().await;
+}
+
+#[tokio::test]
+#[ignore = "needs PostgreSQL at POSTGRES_URL"]
+async fn test_postgres_migrations() {
+    let url = std::env::var("POSTGRES_URL").unwrap_or_else(|_| "postgres://localhost/hal9_test".to_string());
+    let pool = connect(&url).await;
+    let replica = connect(&url).await;
+
+    let (first, second) = tokio::join!(
+        database_migrations::migrate(&pool, LOCK_TIMEOUT),
+        database_migrations::migrate(&replica, LOCK_TIMEOUT),
+    );
+    let status = database_migrations::status(&pool).await.unwrap();
+    assert_eq!(status.state, SchemaState::Current);
+    assert_eq!(status.current, Some(status.expected));
+    let (first, second) = (first.unwrap(), second.unwrap());
+    assert!(first.applied.is_empty() || second.applied.is_empty());
+    pool.close().await;
+    replica.close().await;
+}
@@ -0,0 +1,162 @@
+//! Mock scenario files: replay with captured-group templates, strict mode
+//! failing on drift, and replaying a recorded run
+
+mod common;
+
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde_json::{json, Value};
+
+use hal9_core::{NeuronSignal, ServerConfig};
+use hal9_server::api::create_api_router;
+use hal9_server::chain_tracker::{ChainResult, ChainStatus};
+use hal9_server::server::HAL9Server;
+
+use common::call;
+
+/// A planner forwarding to a coder, answered by `coder_response` unless a
+/// scenario says otherwise
+fn config(coder_response: &str, scenarios: Value) -> ServerConfig {
+    common::mock_config(json!({
+        "server_id": "mock-scenario-test",
+        "neurons": [
+            {"id": "planner", "layer": "L4", "forward_connections": ["coder"], "backward_connections": []},
+            {"id": "coder", "layer": "L2", "forward_connections": [], "backward_connections": ["planner"]},
+        ],
+        "claude": {
+            "mock_responses": {
+                "L4": [{"trigger": "default", "response": "FORWARD_TO: coder\nCONTENT: Build the parser", "delay_ms": 0}],
+                "L2": [{"trigger": "default", "response": coder_response, "delay_ms": 0}],
+            },
+            "scenarios": scenarios,
+        },
+    }))
+}
+
+fn write_scenario(dir: &Path, name: &str, scenario: &str) {
+    std::fs::write(dir.join(format!("{}.yaml", name)), scenario).unwrap();
+}
+
+async fn run_chain(server: &HAL9Server, content: &str) -> ChainResult {
+    let signal = NeuronSignal::forward("api-client", "planner", "API", "L4", content.to_string());
+    let chain_id = server.submit_signal(signal).await.unwrap();
+    tokio::time::timeout(Duration::from_secs(10), async {
+        loop {
+            let result = server.get_chain_result(&chain_id).await.unwrap();
+            if result.status != ChainStatus::Running {
+                return result;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("chain did not finish")
+}
+
+#[tokio::test]
+async fn test_responses_are_expanded_with_captured_groups() {
+    let dir = tempfile::tempdir().unwrap();
+    write_scenario(dir.path(), "parser", r#"
+neurons:
+  planner:
+    - match: {regex: 'Content: Build the (?P<what>\w+) in (\w+)'}
+      response: "FORWARD_TO: coder\nCONTENT:\nWrite the ${what} in $2"
+  coder:
+    - match: {regex: 'Content: Write the (?P<what>\w+) in (?P<lang>\w+)'}
+      response: "RESULT: ${lang} ${what} done for $$5"
+"#);
+    let server = Arc::new(HAL9Server::new(config("RESULT: unscripted", json!({"dir": dir.path()}))));
+    server.start().await.unwrap();
+    let app = create_api_router(server.clone());
+
+    // Selected from the admin API; until then the mock responses answer
+    let (_, body) = call(&app, "PUT", "/api/v1/admin/mock/scenario", Some(json!({"name": "parser"}))).await;
+    assert_eq!(body["data"]["active"]["name"], "parser", "{}", body);
+    assert_eq!(body["data"]["available"], json!(["parser"]));
+
+    let result = run_chain(&server, "Build the lexer in Rust").await;
+    assert_eq!(result.status, ChainStatus::Completed, "{:?}", result.errors);
+    assert_eq!(result.final_output.as_deref(), Some("RESULT: Rust lexer done for $5"));
+
+    let status = server.mock_scenarios();
+    let progress = &status.active.unwrap().neurons["coder"];
+    assert_eq!((progress.matched, progress.expected), (1, 1));
+
+    // Past the end of a lenient scenario the mock responses answer again;
+    // a new prompt, as neurons cache responses to ones they have seen
+    let result = run_chain(&server, "Build the lexer in Go").await;
+    assert_eq!(result.final_output.as_deref(), Some("RESULT: unscripted"));
+
+    // Unknown and unsafe names are refused
+    let (_, body) = call(&app, "PUT", "/api/v1/admin/mock/scenario", Some(json!({"name": "missing"}))).await;
+    assert_eq!(body["success"], false);
+    let (_, body) = call(&app, "PUT", "/api/v1/admin/mock/scenario", Some(json!({"name": "../parser"}))).await;
+    assert_eq!(body["success"], false);
+
+    server.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_strict_scenario_fails_prompts_that_drift() {
+    let dir = tempfile::tempdir().unwrap();
+    write_scenario(dir.path(), "lexer", r#"
+strict: true
+neurons:
+  planner:
+    - match: {regex: 'Content: Build the lexer'}
+      response: "FORWARD_TO: coder\nCONTENT:\nWrite the lexer"
+  coder:
+    - match: {regex: 'Content: Write the lexer'}
+      response: "RESULT: lexer done"
+"#);
+    let scenarios = json!({"dir": dir.path(), "active": "lexer"});
+    let server = Arc::new(HAL9Server::new(config("RESULT: unscripted", scenarios)));
+    server.start().await.unwrap();
+
+    // The prompt changed since the scenario was written
+    let result = run_chain(&server, "Build the parser").await;
+    assert_eq!(result.status, ChainStatus::Failed);
+    assert!(result.final_output.is_none());
+    let error = result.errors.join("\n");
+    assert!(error.contains("Mock scenario lexer drifted"), "{}", error);
+    assert!(error.contains("prompt 1 of planner"), "{}", error);
+
+    server.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_recorded_run_replays_the_same_chain() {
+    let dir = tempfile::tempdir().unwrap();
+    let recording = dir.path().join("recorded.yaml");
+
+    let live = config("RESULT: Built it, cost $3", json!({"record_to": recording}));
+    let server = Arc::new(HAL9Server::new(live));
+    server.start().await.unwrap();
+    let recorded = run_chain(&server, "Build the parser").await;
+    assert_eq!(recorded.status, ChainStatus::Completed, "{:?}", recorded.errors);
+    assert_eq!(server.mock_scenarios().recording.unwrap().exchanges, 2);
+    server.shutdown().await.unwrap();
+    assert!(recording.is_file());
+
+    // Different mock responses, so only the replay can reproduce the run
+    let replay = config("RESULT: something else", json!({"dir": dir.path(), "active": "recorded"}));
+    let server = Arc::new(HAL9Server::new(replay));
+    server.start().await.unwrap();
+    let status = server.mock_scenarios();
+    assert!(status.active.as_ref().unwrap().strict, "recordings replay strictly");
+
+    let replayed = run_chain(&server, "Build the parser").await;
+    assert_eq!(replayed.status, ChainStatus::Completed, "{:?}", replayed.errors);
+    assert_eq!(replayed.final_output, recorded.final_output);
+    assert_eq!(replayed.final_output.as_deref(), Some("RESULT: Built it, cost $3"));
+    assert_eq!(replayed.layers, recorded.layers);
+    assert_eq!(replayed.steps_completed, recorded.steps_completed);
+
+    // The recording holds nothing more, so another run drifts
+    let again = run_chain(&server, "Build the lexer").await;
+    assert_eq!(again.status, ChainStatus::Failed);
+
+    server.shutdown().await.unwrap();
+}
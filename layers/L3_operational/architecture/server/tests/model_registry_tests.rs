@@ -0,0 +1,142 @@
+//! Model lifecycle: deprecation warnings, remapping of retired models and
+//! the failure of retired models without a replacement
+
+mod common;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use chrono::NaiveDate;
+use serde_json::json;
+
+use hal9_core::config::{ModelEntryConfig, ModelLifecycle, ModelsConfig};
+use hal9_core::metadata_schema::keys;
+use hal9_core::Error;
+use hal9_server::claude::{model_pricing, ClaudeAPIClient, ClaudeInterface};
+use hal9_server::model_registry::{self, ModelRegistry, ModelResolution, ModelStatus};
+
+fn day(y: i32, m: u32, d: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(y, m, d).unwrap()
+}
+
+fn models_config(remap: &[(&str, &str)]) -> ModelsConfig {
+    ModelsConfig {
+        remap: remap.iter().map(|(from, to)| (from.to_string(), to.to_string())).collect(),
+        registry: vec![
+            ModelEntryConfig { id: "test-old".to_string(), status: ModelLifecycle::Retired, sunset: None },
+            ModelEntryConfig {
+                id: "test-sunsetting".to_string(),
+                status: ModelLifecycle::Deprecated,
+                sunset: Some(day(2025, 3, 1)),
+            },
+            ModelEntryConfig { id: "test-new".to_string(), status: ModelLifecycle::Active, sunset: None },
+        ],
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_deprecated_models_warn_with_days_to_sunset() {
+    let registry = ModelRegistry::new(&models_config(&[("test-sunsetting", "test-new")]));
+    let models = vec!["test-new".to_string(), "test-sunsetting".to_string(), "unlisted".to_string()];
+
+    let checks = registry.check(&models, day(2025, 2, 19));
+    assert_eq!(checks[0].status, ModelStatus::Active);
+    assert_eq!(checks[1].status, ModelStatus::Deprecated { sunset: day(2025, 3, 1), days_to_sunset: 10 });
+    assert_eq!(checks[1].replacement, None);
+    assert_eq!(checks[2].status, ModelStatus::Unknown);
+
+    // From the sunset date on the model is retired and calls go to its remap
+    let checks = registry.check(&models, day(2025, 3, 1));
+    assert_eq!(checks[1].status, ModelStatus::Retired);
+    assert_eq!(checks[1].replacement.as_deref(), Some("test-new"));
+}
+
+#[test]
+fn test_retired_model_resolves_through_remap_chain() {
+    let registry = ModelRegistry::new(&models_config(&[("test-old", "test-sunsetting"), ("test-sunsetting", "test-new")]));
+
+    // Before the sunset the first replacement still serves
+    let resolution = registry.resolve_on("test-old", day(2025, 1, 1)).unwrap();
+    assert_eq!(resolution, ModelResolution {
+        model: "test-sunsetting".to_string(),
+        remapped_from: Some("test-old".to_string()),
+    });
+    let resolution = registry.resolve_on("test-old", day(2025, 6, 1)).unwrap();
+    assert_eq!(resolution.model, "test-new");
+
+    let resolution = registry.resolve_on("test-new", day(2025, 6, 1)).unwrap();
+    assert_eq!(resolution.remapped_from, None);
+}
+
+#[test]
+fn test_remap_is_recorded_in_metadata() {
+    let registry = ModelRegistry::new(&models_config(&[("test-old", "test-new")]));
+    let mut metadata = HashMap::new();
+    registry.resolve("test-new").unwrap().apply_to(&mut metadata);
+    assert!(metadata.is_empty());
+
+    registry.resolve("test-old").unwrap().apply_to(&mut metadata);
+    assert_eq!(metadata[keys::model::REQUESTED], "test-old");
+    assert_eq!(metadata[keys::model::REMAPPED_TO], "test-new");
+}
+
+#[test]
+fn test_pricing_follows_remap() {
+    let config = ModelsConfig {
+        remap: HashMap::from([("claude-3-sonnet-20240229".to_string(), "claude-3-haiku-20240307".to_string())]),
+        ..Default::default()
+    };
+    let registry = ModelRegistry::new(&config);
+    assert_eq!(registry.billed_model("claude-3-sonnet-20240229"), "claude-3-haiku-20240307");
+    assert_eq!(registry.pricing("claude-3-sonnet-20240229"), model_pricing("claude-3-haiku-20240307"));
+
+    // Without a replacement the configured model is billed
+    let registry = ModelRegistry::default();
+    assert_eq!(registry.billed_model("claude-3-sonnet-20240229"), "claude-3-sonnet-20240229");
+}
+
+fn api_client(model: &str, registry: ModelRegistry) -> ClaudeAPIClient {
+    let mut client = ClaudeAPIClient::new("test-key".to_string(), model.to_string(), "L2", 0.7, 100);
+    client.set_model_registry(Arc::new(registry));
+    client
+}
+
+#[tokio::test]
+async fn test_api_client_calls_the_replacement() {
+    let client = api_client("test-old", ModelRegistry::new(&models_config(&[("test-old", "test-new")])));
+    let resolution = client.effective_model().await.unwrap();
+    assert_eq!(resolution.model, "test-new");
+    assert_eq!(resolution.remapped_from.as_deref(), Some("test-old"));
+}
+
+#[tokio::test]
+async fn test_unmapped_retired_model_fails_before_the_provider() {
+    let client = api_client("test-old", ModelRegistry::new(&models_config(&[])));
+    let err = client.send_message("hello").await.unwrap_err();
+    assert!(matches!(&err, Error::ModelRetired { model } if model == "test-old"), "{:?}", err);
+    assert_eq!(err.class(), "model_retired");
+    assert!(client.last_token_usage().is_none());
+}
+
+#[test]
+fn test_configured_models_skip_local_models() {
+    let config = common::mock_config(json!({
+        "server_id": "models-test",
+        "claude": {"mode": "api", "model": "claude-3-sonnet-20240229"},
+        "output_format": {"reformat_model": "local/llama"},
+    }));
+    assert_eq!(model_registry::configured_models(&config), vec!["claude-3-sonnet-20240229".to_string()]);
+}
+
+#[test]
+fn test_validate_rejects_loops_and_undated_deprecations() {
+    assert!(model_registry::validate(&models_config(&[("test-old", "test-new")])).is_ok());
+
+    let looping = models_config(&[("test-old", "test-new"), ("test-new", "test-old")]);
+    assert!(matches!(model_registry::validate(&looping), Err(Error::Config(msg)) if msg.contains("loops")));
+
+    let mut undated = models_config(&[]);
+    undated.registry[1].sunset = None;
+    assert!(matches!(model_registry::validate(&undated), Err(Error::Config(msg)) if msg.contains("sunset")));
+}
@@ -0,0 +1,174 @@
+//! Compression and batching negotiated between network transports
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use hal9_core::metadata_schema::keys;
+use hal9_core::NeuronSignal;
+use hal9_server::metrics::Metrics;
+use hal9_server::network::protocol::{Compression, COMPRESSED_FLAG};
+use hal9_server::network::tcp_transport::HIGH_PRIORITY;
+use hal9_server::network::{MessageCodec, NetworkMessage, TcpTransport, TransportConfig};
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpStream;
+use tokio::sync::mpsc;
+
+type Signals = mpsc::Receiver<(String, NeuronSignal)>;
+
+fn config() -> TransportConfig {
+    TransportConfig {
+        bind_address: "127.0.0.1:0".parse().unwrap(),
+        compression: Some(Compression { level: 3, threshold: 1024 }),
+        ..Default::default()
+    }
+}
+
+struct Node {
+    transport: TcpTransport,
+    metrics: Arc<Metrics>,
+    signals: Signals,
+}
+
+impl Node {
+    async fn start(server_id: &str, config: TransportConfig) -> Self {
+        let metrics = Arc::new(Metrics::new());
+        let mut transport = TcpTransport::new(config, server_id.to_string());
+        transport.set_metrics(metrics.clone());
+        transport.start().await.unwrap();
+        let signals = transport.signal_receiver().await.unwrap();
+
+        Self { transport, metrics, signals }
+    }
+
+    async fn connect(&self, other: &Node, other_id: &str) {
+        self.transport.connect(other.transport.local_addr().unwrap(), other_id).await.unwrap();
+    }
+
+    async fn next_signal(&mut self) -> NeuronSignal {
+        tokio::time::timeout(Duration::from_secs(2), self.signals.recv())
+            .await
+            .expect("signal not received")
+            .unwrap()
+            .1
+    }
+}
+
+/// Text that compresses well, like the code L2 neurons exchange
+fn large_content() -> String {
+    (0..400).map(|i| format!("fn step_{}() -> usize {{ {} }}\n", i, i)).collect()
+}
+
+fn signal(content: &str) -> NeuronSignal {
+    NeuronSignal::forward("n-a", "n-b", "L4", "L3", content.to_string())
+}
+
+#[tokio::test]
+async fn test_large_signals_are_compressed() {
+    let mut server = Node::start("server-b", config()).await;
+    let client = Node::start("server-a", config()).await;
+    client.connect(&server, "server-b").await;
+
+    let content = large_content();
+    client.transport.send_signal("server-b", signal(&content)).await.unwrap();
+    client.transport.send_signal("server-b", signal("small")).await.unwrap();
+
+    assert_eq!(server.next_signal().await.payload.activation.content, content);
+    assert_eq!(server.next_signal().await.payload.activation.content, "small");
+
+    // Only the large signal went out compressed
+    let sent = client.metrics.snapshot().transport.sent;
+    assert_eq!((sent.messages, sent.compressed_messages), (2, 1));
+    assert!(sent.wire_bytes * 2 < sent.uncompressed_bytes, "{:?}", sent);
+    assert_eq!(server.metrics.snapshot().transport.received, sent);
+}
+
+#[tokio::test]
+async fn test_compression_needs_both_peers() {
+    let disabled = TransportConfig { compression: None, ..config() };
+    let mut server = Node::start("server-b", disabled).await;
+    let client = Node::start("server-a", config()).await;
+    client.connect(&server, "server-b").await;
+
+    let content = large_content();
+    client.transport.send_signal("server-b", signal(&content)).await.unwrap();
+
+    assert_eq!(server.next_signal().await.payload.activation.content, content);
+    let sent = client.metrics.snapshot().transport.sent;
+    assert_eq!(sent.compressed_messages, 0);
+    assert_eq!(sent.wire_bytes, sent.uncompressed_bytes);
+}
+
+#[tokio::test]
+async fn test_peer_without_capabilities_gets_plain_frames() {
+    let batching = TransportConfig { batch_window: Duration::from_secs(5), ..config() };
+    let server = Node::start("server-b", batching).await;
+
+    // A server from before compression and batching: its hello offers neither
+    let mut legacy = TcpStream::connect(server.transport.local_addr().unwrap()).await.unwrap();
+    let hello = NetworkMessage::Hello {
+        version: "1.0".to_string(),
+        server_id: "server-old".to_string(),
+        capabilities: vec!["signal".to_string(), "metrics".to_string()],
+    };
+    legacy.write_all(&MessageCodec::encode(&hello).unwrap()).await.unwrap();
+    MessageCodec::read_frame(&mut legacy, 65536).await.unwrap();
+
+    tokio::time::timeout(Duration::from_secs(2), async {
+        while !server.transport.is_connected("server-old") {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("legacy peer not registered");
+
+    let content = large_content();
+    server.transport.send_signal("server-old", signal(&content)).await.unwrap();
+
+    // Sent at once, as a plain single-signal frame
+    let frame = tokio::time::timeout(Duration::from_secs(2), MessageCodec::read_frame(&mut legacy, 65536))
+        .await
+        .expect("signal not sent")
+        .unwrap();
+    assert_eq!(u32::from_be_bytes(frame[0..4].try_into().unwrap()) & COMPRESSED_FLAG, 0);
+    match MessageCodec::decode(&frame).unwrap() {
+        NetworkMessage::Signal(received) => assert_eq!(received.payload.activation.content, content),
+        other => panic!("expected a signal, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_signals_are_batched_within_window() {
+    let mut server = Node::start("server-b", config()).await;
+    let batching = TransportConfig { batch_window: Duration::from_millis(50), ..config() };
+    let client = Node::start("server-a", batching).await;
+    client.connect(&server, "server-b").await;
+
+    for content in ["one", "two", "three"] {
+        client.transport.send_signal("server-b", signal(content)).await.unwrap();
+    }
+
+    for content in ["one", "two", "three"] {
+        assert_eq!(server.next_signal().await.payload.activation.content, content);
+    }
+    let transport = client.metrics.snapshot().transport;
+    assert_eq!(transport.sent.messages, 1);
+    assert_eq!((transport.batches_sent, transport.batched_signals), (1, 3));
+}
+
+#[tokio::test]
+async fn test_high_priority_signal_flushes_batch() {
+    let mut server = Node::start("server-b", config()).await;
+    let batching = TransportConfig { batch_window: Duration::from_secs(60), ..config() };
+    let client = Node::start("server-a", batching).await;
+    client.connect(&server, "server-b").await;
+
+    client.transport.send_signal("server-b", signal("queued")).await.unwrap();
+    let mut urgent = signal("urgent");
+    urgent.metadata.insert(keys::network::PRIORITY.to_string(), HIGH_PRIORITY.to_string());
+    client.transport.send_signal("server-b", urgent).await.unwrap();
+
+    // Both arrive well before the window closes, in the order sent
+    assert_eq!(server.next_signal().await.payload.activation.content, "queued");
+    assert_eq!(server.next_signal().await.payload.activation.content, "urgent");
+    assert_eq!(client.metrics.snapshot().transport.batches_sent, 1);
+}
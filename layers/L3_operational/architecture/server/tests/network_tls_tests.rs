@@ -0,0 +1,230 @@
+//! TLS and mutual TLS between network transports, using throwaway CAs
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use hal9_core::NeuronSignal;
+use hal9_server::metrics::Metrics;
+use hal9_server::network::{TcpTransport, TransportConfig, PEER_IDENTITY_KEY};
+use rcgen::{BasicConstraints, Certificate, CertificateParams, DnType, IsCa};
+use tempfile::TempDir;
+
+struct TestCa {
+    cert: Certificate,
+}
+
+impl TestCa {
+    fn new(name: &str) -> Self {
+        let mut params = CertificateParams::new(vec![]);
+        params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        params.distinguished_name.push(DnType::CommonName, name);
+        Self { cert: Certificate::from_params(params).unwrap() }
+    }
+
+    fn pem(&self) -> String {
+        self.cert.serialize_pem().unwrap()
+    }
+
+    /// Certificate and key PEM for a server id, used as both CN and SAN
+    fn issue(&self, server_id: &str) -> (String, String) {
+        let mut params = CertificateParams::new(vec![server_id.to_string()]);
+        params.distinguished_name.push(DnType::CommonName, server_id);
+        let leaf = Certificate::from_params(params).unwrap();
+        (
+            leaf.serialize_pem_with_signer(&self.cert).unwrap(),
+            leaf.serialize_private_key_pem(),
+        )
+    }
+}
+
+/// Write a node's certificate, key and trusted CA into `dir`
+fn write_identity(dir: &Path, issuer: &TestCa, trusted: &TestCa, server_id: &str) {
+    let (cert, key) = issuer.issue(server_id);
+    std::fs::write(dir.join("node.crt"), cert).unwrap();
+    std::fs::write(dir.join("node.key"), key).unwrap();
+    std::fs::write(dir.join("ca.crt"), trusted.pem()).unwrap();
+}
+
+fn tls_config(dir: &Path, mutual: bool) -> TransportConfig {
+    let path = |name: &str| Some(dir.join(name).to_string_lossy().into_owned());
+    TransportConfig {
+        bind_address: "127.0.0.1:0".parse().unwrap(),
+        connection_timeout: Duration::from_secs(2),
+        tls_enabled: true,
+        tls_cert_path: path("node.crt"),
+        tls_key_path: path("node.key"),
+        tls_ca_path: path("ca.crt"),
+        tls_require_client_cert: mutual,
+        ..Default::default()
+    }
+}
+
+struct Node {
+    transport: TcpTransport,
+    metrics: Arc<Metrics>,
+    dir: TempDir,
+}
+
+impl Node {
+    async fn start(
+        server_id: &str,
+        issuer: &TestCa,
+        trusted: &TestCa,
+        mutual: bool,
+        peer_allowed_layers: HashMap<String, Vec<String>>,
+    ) -> Self {
+        let dir = TempDir::new().unwrap();
+        write_identity(dir.path(), issuer, trusted, server_id);
+
+        let config = TransportConfig {
+            peer_allowed_layers,
+            ..tls_config(dir.path(), mutual)
+        };
+        let metrics = Arc::new(Metrics::new());
+        let mut transport = TcpTransport::new(config, server_id.to_string());
+        transport.set_metrics(metrics.clone());
+        transport.start().await.unwrap();
+
+        Self { transport, metrics, dir }
+    }
+
+    async fn connect(&self, other: &Node, other_id: &str) -> hal9_core::Result<()> {
+        self.transport.connect(other.transport.local_addr().unwrap(), other_id).await
+    }
+
+    /// Wait for the failure counter of `reason` to become non-zero; the
+    /// accepting side records it from a background task
+    async fn failed_with(&self, reason: &str) -> bool {
+        for _ in 0..50 {
+            if self.metrics.peer_connection_failures
+                .get(reason)
+                .map(|count| count.load(std::sync::atomic::Ordering::Relaxed) > 0)
+                .unwrap_or(false)
+            {
+                return true;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        false
+    }
+}
+
+async fn next_signal(transport: &TcpTransport) -> Option<(String, NeuronSignal)> {
+    let mut rx = transport.signal_receiver().await?;
+    tokio::time::timeout(Duration::from_secs(2), rx.recv()).await.ok().flatten()
+}
+
+#[tokio::test]
+async fn test_mutual_tls_accepts_peer_and_attaches_identity() {
+    let ca = TestCa::new("hal9-test-ca");
+    let server = Node::start("server-b", &ca, &ca, true, HashMap::new()).await;
+    let client = Node::start("server-a", &ca, &ca, true, HashMap::new()).await;
+
+    client.connect(&server, "server-b").await.unwrap();
+    assert!(client.transport.is_connected("server-b"));
+
+    // A forged identity in the payload is replaced by the certificate's
+    let mut signal = NeuronSignal::forward("n-a", "n-b", "L4", "L3", "hello".to_string());
+    signal.metadata.insert(PEER_IDENTITY_KEY.to_string(), "server-z".to_string());
+    client.transport.send_signal("server-b", signal).await.unwrap();
+
+    let (peer, received) = next_signal(&server.transport).await.expect("signal not received");
+    assert_eq!(peer, "server-a");
+    assert_eq!(received.metadata.get(PEER_IDENTITY_KEY).map(String::as_str), Some("server-a"));
+}
+
+#[tokio::test]
+async fn test_rejects_peer_from_unknown_ca() {
+    let trusted = TestCa::new("hal9-test-ca");
+    let rogue = TestCa::new("rogue-ca");
+    let server = Node::start("server-b", &trusted, &trusted, true, HashMap::new()).await;
+    // Trusts the server, but its own certificate is from a CA the server does not know
+    let client = Node::start("server-a", &rogue, &trusted, true, HashMap::new()).await;
+
+    assert!(client.connect(&server, "server-b").await.is_err());
+    assert!(server.failed_with("auth").await);
+    assert!(!server.transport.is_connected("server-a"));
+}
+
+#[tokio::test]
+async fn test_rejects_peer_not_in_allowed_list() {
+    let ca = TestCa::new("hal9-test-ca");
+    let peers = HashMap::from([("server-c".to_string(), vec!["*".to_string()])]);
+    let server = Node::start("server-b", &ca, &ca, true, peers).await;
+    let client = Node::start("server-a", &ca, &ca, true, HashMap::new()).await;
+
+    let err = client.connect(&server, "server-b").await.unwrap_err();
+    assert!(err.to_string().contains("auth failure"), "{}", err);
+    assert!(client.failed_with("auth").await);
+    assert!(server.failed_with("auth").await);
+}
+
+#[tokio::test]
+async fn test_drops_signals_for_disallowed_layers() {
+    let ca = TestCa::new("hal9-test-ca");
+    let peers = HashMap::from([("server-a".to_string(), vec!["L3".to_string()])]);
+    let server = Node::start("server-b", &ca, &ca, true, peers).await;
+    let client = Node::start("server-a", &ca, &ca, true, HashMap::new()).await;
+    client.connect(&server, "server-b").await.unwrap();
+
+    let denied = NeuronSignal::forward("n-a", "n-b", "L4", "L5", "denied".to_string());
+    let allowed = NeuronSignal::forward("n-a", "n-b", "L4", "L3", "allowed".to_string());
+    client.transport.send_signal("server-b", denied).await.unwrap();
+    client.transport.send_signal("server-b", allowed).await.unwrap();
+
+    // Signals arrive in order, so the first one through must be the allowed one
+    let (_, received) = next_signal(&server.transport).await.expect("signal not received");
+    assert_eq!(received.payload.activation.content, "allowed");
+}
+
+#[tokio::test]
+async fn test_reload_rotates_server_certificate() {
+    let old_ca = TestCa::new("hal9-ca-2025");
+    let new_ca = TestCa::new("hal9-ca-2026");
+    let server = Node::start("server-b", &old_ca, &old_ca, false, HashMap::new()).await;
+
+    let old_client = Node::start("server-a", &old_ca, &old_ca, false, HashMap::new()).await;
+    old_client.connect(&server, "server-b").await.unwrap();
+
+    // Rotate the server onto the new CA
+    write_identity(server.dir.path(), &new_ca, &new_ca, "server-b");
+    server.transport.reload_tls().unwrap();
+
+    // The established connection survives; new connections see the new certificate
+    assert!(old_client.transport.is_connected("server-b"));
+
+    let stale_client = Node::start("server-c", &old_ca, &old_ca, false, HashMap::new()).await;
+    assert!(stale_client.connect(&server, "server-b").await.is_err());
+    assert!(stale_client.failed_with("auth").await);
+
+    let new_client = Node::start("server-d", &new_ca, &new_ca, false, HashMap::new()).await;
+    new_client.connect(&server, "server-b").await.unwrap();
+}
+
+#[tokio::test]
+async fn test_failed_reload_keeps_current_certificate() {
+    let ca = TestCa::new("hal9-test-ca");
+    let server = Node::start("server-b", &ca, &ca, false, HashMap::new()).await;
+
+    std::fs::write(server.dir.path().join("node.key"), "not a key").unwrap();
+    assert!(server.transport.reload_tls().is_err());
+
+    let client = Node::start("server-a", &ca, &ca, false, HashMap::new()).await;
+    client.connect(&server, "server-b").await.unwrap();
+}
+
+#[tokio::test]
+async fn test_network_failure_is_classified() {
+    let ca = TestCa::new("hal9-test-ca");
+    let client = Node::start("server-a", &ca, &ca, false, HashMap::new()).await;
+
+    // Nothing listens here once the probe listener is dropped
+    let addr = {
+        let probe = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        probe.local_addr().unwrap()
+    };
+    assert!(client.transport.connect(addr, "server-b").await.is_err());
+    assert!(client.failed_with("network").await);
+}
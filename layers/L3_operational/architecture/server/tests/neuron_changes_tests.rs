@@ -0,0 +1,186 @@
+//! Neuron change feed: versions bump on state changes only, and a client
+//! following the feed ends up with the registry's neurons
+
+mod common;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use serde_json::json;
+
+use hal9_core::config::ClaudeConfig;
+use hal9_server::{
+    claude::MockClaude,
+    concurrency::{Admission, ConcurrencyPermit},
+    neuron::{ManagedNeuron, NeuronRegistry},
+    neuron_changes::{NeuronChanges, QueueDepth},
+    server::NeuronInfo,
+};
+
+use common::neuron_config;
+
+async fn registry(ids: &[&str]) -> Arc<NeuronRegistry> {
+    let registry = Arc::new(NeuronRegistry::new());
+    for id in ids {
+        let config = neuron_config(id, "L2", &[], json!({"concurrency": {"max_concurrent": 200, "max_queued": 0}}));
+        let claude = MockClaude::new("L2", &ClaudeConfig::default());
+        registry.register(ManagedNeuron::new(config, Box::new(claude)).unwrap()).await.unwrap();
+    }
+    registry
+}
+
+/// Hold `count` processing slots of neuron `id`
+async fn admit(registry: &NeuronRegistry, id: &str, count: usize) -> Vec<ConcurrencyPermit> {
+    let neuron = registry.get(id).unwrap();
+    let mut permits = Vec::new();
+    for _ in 0..count {
+        match neuron.concurrency().admit().await {
+            Admission::Admitted(permit) => permits.push(permit),
+            _ => panic!("{} is full", id),
+        }
+    }
+    permits
+}
+
+fn depth(info: &NeuronInfo) -> QueueDepth {
+    QueueDepth::of(info.concurrency.in_flight + info.concurrency.queued)
+}
+
+/// A client keeping its own copy of the neurons from the change feed
+#[derive(Default)]
+struct Follower {
+    neurons: HashMap<String, NeuronInfo>,
+    version: Option<u64>,
+}
+
+impl Follower {
+    fn follow(&mut self, changes: NeuronChanges) {
+        if let Some(version) = self.version {
+            assert!(changes.version >= version, "{} after {}", changes.version, version);
+        }
+        if changes.full {
+            self.neurons.clear();
+        }
+        for id in &changes.removed {
+            self.neurons.remove(id);
+        }
+        for info in changes.changed {
+            self.neurons.insert(info.id.clone(), info);
+        }
+        self.version = Some(changes.version);
+    }
+}
+
+#[test]
+fn test_queue_depth_buckets() {
+    let buckets: Vec<_> = [0, 1, 10, 11, 100, 101, 5000].into_iter().map(QueueDepth::of).collect();
+    assert_eq!(buckets, vec![
+        QueueDepth::Empty,
+        QueueDepth::Low,
+        QueueDepth::Low,
+        QueueDepth::Medium,
+        QueueDepth::Medium,
+        QueueDepth::High,
+        QueueDepth::High,
+    ]);
+}
+
+#[tokio::test]
+async fn test_queue_fluctuations_within_a_bucket_keep_the_version() {
+    let registry = registry(&["planner", "coder"]).await;
+
+    let all = registry.changes_since(None).await;
+    assert!(all.full);
+    assert_eq!(all.changed.iter().map(|info| info.id.as_str()).collect::<Vec<_>>(), vec!["coder", "planner"]);
+    let version = all.version;
+    assert!(registry.changes_since(Some(version)).await.is_empty());
+
+    // Anywhere from 1 to 10 signals is the same bucket
+    let mut held = admit(&registry, "coder", 1).await;
+    let low = registry.refresh_changes().await;
+    assert!(low > version);
+    for count in [7, 3, 10, 1, 4, 9, 2] {
+        held.truncate(held.len().min(count));
+        let missing = count - held.len();
+        held.extend(admit(&registry, "coder", missing).await);
+        assert_eq!(registry.refresh_changes().await, low, "{} signals", count);
+    }
+    assert!(registry.changes_since(Some(low)).await.is_empty());
+
+    // The next bucket bumps the version and lists the neuron as it is now
+    held.extend(admit(&registry, "coder", 11 - held.len()).await);
+    let changes = registry.changes_since(Some(low)).await;
+    assert!(!changes.full && changes.version > low);
+    assert_eq!(changes.changed.len(), 1);
+    assert_eq!(changes.changed[0].concurrency.in_flight, 11);
+    assert_eq!(depth(&changes.changed[0]), QueueDepth::Medium);
+
+    // Missed versions are covered by asking from an older one
+    drop(held);
+    let idle = registry.changes_since(Some(version)).await;
+    assert_eq!(idle.changed.len(), 1);
+    assert_eq!(depth(&idle.changed[0]), QueueDepth::Empty);
+
+    // Removed neurons are reported by ID, and unknown versions get every neuron
+    registry.remove("planner").await.unwrap();
+    let removed = registry.changes_since(Some(idle.version)).await;
+    assert_eq!((removed.changed.len(), removed.removed.clone()), (0, vec!["planner".to_string()]));
+    let unknown = registry.changes_since(Some(removed.version + 10)).await;
+    assert!(unknown.full);
+    assert_eq!(unknown.changed.len(), 1);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_follower_converges_under_concurrent_updates() {
+    let ids: Vec<String> = (0..8).map(|i| format!("neuron-{}", i)).collect();
+    let registry = registry(&ids.iter().map(String::as_str).collect::<Vec<_>>()).await;
+
+    // Each neuron's load moves through every bucket while snapshots are
+    // taken from several tasks at once
+    let mut updates = Vec::new();
+    for (i, id) in ids.iter().enumerate() {
+        let registry = registry.clone();
+        let id = id.clone();
+        updates.push(tokio::spawn(async move {
+            for round in 0..20 {
+                let count = [0, 5, 50, 150, 3][(i + round) % 5];
+                let held = admit(&registry, &id, count).await;
+                registry.refresh_changes().await;
+                tokio::task::yield_now().await;
+                drop(held);
+            }
+        }));
+    }
+    let refreshers: Vec<_> = (0..3)
+        .map(|_| {
+            let registry = registry.clone();
+            tokio::spawn(async move {
+                for _ in 0..50 {
+                    registry.refresh_changes().await;
+                    tokio::task::yield_now().await;
+                }
+            })
+        })
+        .collect();
+
+    let mut follower = Follower::default();
+    while !updates.iter().all(|update| update.is_finished()) {
+        follower.follow(registry.changes_since(follower.version).await);
+        tokio::task::yield_now().await;
+    }
+    for task in updates.into_iter().chain(refreshers) {
+        task.await.unwrap();
+    }
+    follower.follow(registry.changes_since(follower.version).await);
+    let seen = &follower.neurons;
+
+    let current: HashMap<String, NeuronInfo> =
+        registry.list_all().await.into_iter().map(|info| (info.id.clone(), info)).collect();
+    assert_eq!(seen.len(), ids.len());
+    for (id, info) in &current {
+        assert_eq!(depth(&seen[id]), depth(info), "{}", id);
+        assert_eq!(depth(info), QueueDepth::Empty);
+        assert_eq!((&seen[id].state, seen[id].is_healthy), (&info.state, info.is_healthy));
+    }
+    assert!(registry.changes_since(follower.version).await.is_empty());
+}
@@ -0,0 +1,314 @@
+//! Organization usage reports against hand-computed fixtures
+
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use sqlx::SqlitePool;
+
+use hal9_core::auth::{Permission, Permissions};
+use hal9_core::{config::BudgetPeriod, NeuronSignal};
+use hal9_server::{
+    api_orgs::authorize,
+    auth_middleware::AuthUser,
+    chain_tracker::{ChainRecord, ChainStatus, ChainTracker, ORG_ID_KEY, PARENT_ID_KEY, USER_ID_KEY},
+    concurrency::DeadLetter,
+    error::ServerError,
+    org_usage::{parse_period, to_csv, OrgUsage, OrgUsageStore},
+    webhooks::{Delivery, DeliveryStatus, FinishedDelivery},
+};
+
+const HAIKU: &str = "claude-3-haiku-20240307";
+const SONNET: &str = "claude-3-sonnet-20240229";
+
+fn at(month: u32, day: u32) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2026, month, day, 10, 0, 0).unwrap()
+}
+
+fn now() -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2026, 10, 15, 12, 0, 0).unwrap()
+}
+
+fn assert_close(actual: f64, expected: f64) {
+    assert!((actual - expected).abs() < 1e-12, "{} != {}", actual, expected);
+}
+
+fn root_signal(org: Option<&str>, user: &str, layer: &str) -> NeuronSignal {
+    let mut signal = NeuronSignal::forward("api", "planner", "API", layer, "work".into());
+    if let Some(org) = org {
+        signal.metadata.insert(ORG_ID_KEY.to_string(), org.to_string());
+    }
+    signal.metadata.insert(USER_ID_KEY.to_string(), user.to_string());
+    signal
+}
+
+/// One step of a scripted chain: layer, model, tokens and whether it failed
+struct Step(&'static str, Option<&'static str>, u32, u32, bool);
+
+/// Run `steps` through a tracker as a root with one child per further
+/// step, dated `created_at` and taking `duration_ms`. With `finish` unset
+/// the root expects one more child than it gets, so the chain stays running.
+fn chain(org: Option<&str>, user: &str, created_at: DateTime<Utc>, duration_ms: i64, steps: &[Step], finish: bool) -> ChainRecord {
+    let tracker = ChainTracker::new();
+    let mut root = root_signal(org, user, steps[0].0);
+    let chain_id = tracker.start(&mut root);
+    let children = steps.len() - 1 + usize::from(!finish);
+
+    for (i, Step(layer, model, prompt, completion, failed)) in steps.iter().enumerate() {
+        let signal = if i == 0 {
+            root.clone()
+        } else {
+            let mut child = NeuronSignal::forward("planner", "worker", "L4", layer, "work".into());
+            child.metadata = root.metadata.clone();
+            child.metadata.insert(PARENT_ID_KEY.to_string(), root.signal_id.to_string());
+            child
+        };
+        tracker.record_usage(&signal, *prompt, *completion);
+        if let Some(model) = model {
+            tracker.record_model(&signal, model);
+        }
+        let outcome = if *failed { Err("provider overloaded") } else { Ok("done") };
+        tracker.record_step(&signal, outcome, if i == 0 { children } else { 0 });
+    }
+
+    let mut record = tracker.get(&chain_id).unwrap();
+    record.created_at = created_at;
+    record.completed_at = record.completed_at.map(|_| created_at + Duration::milliseconds(duration_ms));
+    record
+}
+
+fn delivery(owner: &str, status: DeliveryStatus, created_at: DateTime<Utc>) -> FinishedDelivery {
+    FinishedDelivery {
+        owner: owner.to_string(),
+        delivery: Delivery {
+            id: uuid::Uuid::new_v4().to_string(),
+            webhook_id: "wh".to_string(),
+            event: "chain.completed".to_string(),
+            status,
+            attempts: vec![],
+            next_attempt_at: None,
+            created_at,
+        },
+    }
+}
+
+fn dead_letter(org: Option<&str>, timestamp: DateTime<Utc>) -> DeadLetter {
+    DeadLetter {
+        neuron_id: "worker".to_string(),
+        reason: "recovery_exhausted".to_string(),
+        timestamp,
+        signal: root_signal(org, "alice", "L2"),
+    }
+}
+
+async fn store() -> OrgUsageStore {
+    let pool = SqlitePool::connect("sqlite::memory:").await.unwrap();
+    let store = OrgUsageStore::new(pool, SONNET);
+    store.initialize().await.unwrap();
+    store
+}
+
+/// Seed acme's October and September, another organization and unowned
+/// rows that must not count
+async fn seeded_store() -> OrgUsageStore {
+    let store = store().await;
+
+    // Completed in 4s: haiku root 0.00075, default-model child 0.0045
+    let completed = chain(
+        Some("acme"),
+        "alice",
+        at(10, 2),
+        4000,
+        &[Step("L4", Some(HAIKU), 1000, 400, false), Step("L2", None, 500, 200, false)],
+        true,
+    );
+    // Failed in 2s: 0.006
+    let failed = chain(Some("acme"), "bob", at(10, 5), 2000, &[Step("L2", Some(SONNET), 2000, 0, true)], true);
+    // Still running, so only counted as started
+    let running = chain(Some("acme"), "alice", at(10, 10), 0, &[Step("L4", Some(HAIKU), 400, 100, false)], false);
+    // September: 0.002 in 1s
+    let previous = chain(Some("acme"), "carol", at(9, 20), 1000, &[Step("L2", Some(HAIKU), 4000, 800, false)], true);
+    let other_org = chain(Some("globex"), "dave", at(10, 3), 500, &[Step("L2", Some(SONNET), 9000, 9000, false)], true);
+    let no_org = chain(None, "erin", at(10, 3), 500, &[Step("L2", Some(SONNET), 9000, 9000, false)], true);
+
+    for record in [&completed, &failed, &running, &previous, &other_org, &no_org] {
+        store.record_chain_started(record).await.unwrap();
+    }
+    for record in [&completed, &failed, &previous, &other_org, &no_org] {
+        store.record_chain(record).await.unwrap();
+    }
+
+    for (owner, status, created_at) in [
+        ("acme", DeliveryStatus::Delivered, at(10, 2)),
+        ("acme", DeliveryStatus::Delivered, at(10, 5)),
+        ("acme", DeliveryStatus::Delivered, at(10, 6)),
+        ("acme", DeliveryStatus::DeadLettered, at(10, 7)),
+        ("acme", DeliveryStatus::Retrying, at(10, 8)),
+        ("acme", DeliveryStatus::Delivered, at(9, 20)),
+        ("globex", DeliveryStatus::DeadLettered, at(10, 3)),
+    ] {
+        store.record_delivery(&delivery(owner, status, created_at)).await.unwrap();
+    }
+
+    for (org, timestamp) in [(Some("acme"), at(10, 5)), (Some("acme"), at(10, 9)), (Some("globex"), at(10, 3)), (None, at(10, 3))] {
+        store.record_dead_letter(&dead_letter(org, timestamp)).await.unwrap();
+    }
+    store
+}
+
+fn assert_october(usage: &OrgUsage) {
+    assert_eq!(usage.period_start, Utc.with_ymd_and_hms(2026, 10, 1, 0, 0, 0).unwrap());
+    assert_eq!(usage.period_end, Utc.with_ymd_and_hms(2026, 11, 1, 0, 0, 0).unwrap());
+    assert_eq!((usage.chains_started, usage.chains_completed, usage.chains_failed), (3, 1, 1));
+    assert_eq!((usage.prompt_tokens, usage.completion_tokens), (3500, 600));
+    assert_close(usage.cost_usd, 0.01125);
+
+    let layers: Vec<_> = usage.by_layer.iter().map(|b| (b.name.as_str(), b.prompt_tokens, b.completion_tokens)).collect();
+    assert_eq!(layers, vec![("L2", 2500, 200), ("L4", 1000, 400)]);
+    assert_close(usage.by_layer[0].cost_usd, 0.0105);
+    assert_close(usage.by_layer[1].cost_usd, 0.00075);
+
+    let models: Vec<_> = usage.by_model.iter().map(|b| (b.name.as_str(), b.prompt_tokens, b.completion_tokens)).collect();
+    assert_eq!(models, vec![(HAIKU, 1000, 400), (SONNET, 2500, 200)]);
+    assert_close(usage.by_model[0].cost_usd, 0.00075);
+    assert_close(usage.by_model[1].cost_usd, 0.0105);
+
+    let users: Vec<_> = usage.top_users.iter().map(|u| (u.user_id.as_str(), u.chains, u.tokens)).collect();
+    assert_eq!(users, vec![("bob", 1, 2000), ("alice", 1, 2100)]);
+    assert_close(usage.top_users[0].cost_usd, 0.006);
+    assert_close(usage.top_users[1].cost_usd, 0.00525);
+
+    assert_eq!(usage.webhook_deliveries, 4);
+    assert_close(usage.webhook_success_rate.unwrap(), 0.75);
+    assert_eq!(usage.dead_letters, 2);
+    assert_close(usage.avg_chain_latency_ms.unwrap(), 3000.0);
+}
+
+#[tokio::test]
+async fn test_report_aggregates_only_the_org_and_period() {
+    let store = seeded_store().await;
+    let usage = store.report("acme", BudgetPeriod::Monthly, now(), false).await.unwrap();
+    assert_eq!(usage.org_id, "acme");
+    assert_october(&usage);
+    assert!(usage.comparison.is_none());
+
+    // September on its own
+    let september = store.report("acme", BudgetPeriod::Monthly, at(9, 25), false).await.unwrap();
+    assert_eq!((september.chains_started, september.chains_completed, september.chains_failed), (1, 1, 0));
+    assert_eq!((september.prompt_tokens, september.completion_tokens), (4000, 800));
+    assert_close(september.cost_usd, 0.002);
+    assert_close(september.webhook_success_rate.unwrap(), 1.0);
+    assert_eq!(september.dead_letters, 0);
+    assert_close(september.avg_chain_latency_ms.unwrap(), 1000.0);
+
+    // A day without activity has no rates to report
+    let quiet = store.report("acme", BudgetPeriod::Daily, now(), false).await.unwrap();
+    assert_eq!((quiet.chains_started, quiet.prompt_tokens, quiet.webhook_deliveries), (0, 0, 0));
+    assert!(quiet.by_layer.is_empty() && quiet.top_users.is_empty());
+    assert!(quiet.webhook_success_rate.is_none() && quiet.avg_chain_latency_ms.is_none());
+
+    let other = store.report("globex", BudgetPeriod::Monthly, now(), false).await.unwrap();
+    assert_eq!((other.chains_started, other.prompt_tokens, other.dead_letters), (1, 9000, 1));
+    assert_close(other.webhook_success_rate.unwrap(), 0.0);
+}
+
+#[tokio::test]
+async fn test_previous_period_comparison() {
+    let store = seeded_store().await;
+    let usage = store.report("acme", BudgetPeriod::Monthly, now(), true).await.unwrap();
+    assert_october(&usage);
+
+    let comparison = usage.comparison.unwrap();
+    assert_eq!(comparison.period_start, Utc.with_ymd_and_hms(2026, 9, 1, 0, 0, 0).unwrap());
+    assert_eq!(comparison.period_end, usage.period_start);
+    let deltas = comparison.deltas;
+    assert_eq!((deltas.chains_started, deltas.chains_completed, deltas.chains_failed), (2, 0, 1));
+    assert_eq!(deltas.tokens, -700);
+    assert_close(deltas.cost_usd, 0.00925);
+    assert_close(deltas.webhook_success_rate.unwrap(), -0.25);
+    assert_eq!(deltas.dead_letters, 2);
+    assert_close(deltas.avg_chain_latency_ms.unwrap(), 2000.0);
+}
+
+#[tokio::test]
+async fn test_finished_chain_replaces_its_running_row() {
+    let store = store().await;
+    let running = chain(Some("acme"), "alice", at(10, 2), 0, &[Step("L4", Some(HAIKU), 400, 100, false)], false);
+    store.record_chain_started(&running).await.unwrap();
+    store.record_chain(&running).await.unwrap();
+    let mut finished = running.clone();
+    finished.status = ChainStatus::Completed;
+    finished.completed_at = Some(finished.created_at + Duration::milliseconds(1500));
+    store.record_chain(&finished).await.unwrap();
+
+    let usage = store.report("acme", BudgetPeriod::Monthly, now(), false).await.unwrap();
+    assert_eq!((usage.chains_started, usage.chains_completed), (1, 1));
+    // Steps recorded twice are counted once
+    assert_eq!((usage.prompt_tokens, usage.completion_tokens), (400, 100));
+    assert_close(usage.avg_chain_latency_ms.unwrap(), 1500.0);
+}
+
+#[tokio::test]
+async fn test_reports_are_cached() {
+    let store = store().await;
+    let first = store.report("acme", BudgetPeriod::Monthly, now(), false).await.unwrap();
+    assert_eq!(first.chains_started, 0);
+
+    let record = chain(Some("acme"), "alice", at(10, 2), 100, &[Step("L2", None, 10, 10, false)], true);
+    store.record_chain(&record).await.unwrap();
+    let cached = store.report("acme", BudgetPeriod::Monthly, now(), false).await.unwrap();
+    assert_eq!(cached, first);
+
+    // Comparisons are cached apart
+    let compared = store.report("acme", BudgetPeriod::Monthly, now(), true).await.unwrap();
+    assert_eq!(compared.chains_started, 1);
+}
+
+#[tokio::test]
+async fn test_csv_export() {
+    let store = seeded_store().await;
+    let usage = store.report("acme", BudgetPeriod::Monthly, now(), true).await.unwrap();
+    let csv = to_csv(&usage);
+    let lines: Vec<_> = csv.lines().collect();
+    assert_eq!(lines[0], "metric,dimension,value,delta");
+    for expected in [
+        "chains_started,,3,2",
+        "chains_failed,,1,1",
+        "tokens,,4100,-700",
+        "prompt_tokens,layer:L2,2500,",
+        "completion_tokens,model:claude-3-haiku-20240307,400,",
+        "cost_usd,user:bob,0.006,",
+        "webhook_success_rate,,0.75,-0.25",
+        "dead_letters,,2,2",
+        "avg_chain_latency_ms,,3000,2000",
+    ] {
+        assert!(lines.contains(&expected), "missing {}:\n{}", expected, csv);
+    }
+
+    let plain = to_csv(&OrgUsage { comparison: None, ..usage });
+    assert!(plain.starts_with("metric,dimension,value\nchains_started,,3\n"), "{}", plain);
+}
+
+fn admin(org_id: Option<&str>, permissions: Vec<Permission>) -> AuthUser {
+    AuthUser {
+        user_id: "u1".to_string(),
+        username: "admin".to_string(),
+        role: "admin".to_string(),
+        permissions: Permissions::with_permissions(permissions),
+        org_id: org_id.map(str::to_string),
+        api_key_id: None,
+    }
+}
+
+#[test]
+fn test_only_the_orgs_admins_may_read_it() {
+    assert!(authorize(&admin(Some("acme"), vec![Permission::ViewOrgUsage]), "acme").is_ok());
+    assert!(matches!(
+        authorize(&admin(Some("globex"), vec![Permission::ViewOrgUsage]), "acme"),
+        Err(ServerError::Forbidden(_))
+    ));
+    // Callers outside any organization need system admin
+    assert!(authorize(&admin(None, vec![Permission::ViewOrgUsage]), "acme").is_err());
+    assert!(authorize(&admin(None, vec![Permission::ViewOrgUsage, Permission::SystemAdmin]), "acme").is_ok());
+
+    assert_eq!(parse_period("month").unwrap(), BudgetPeriod::Monthly);
+    assert_eq!(parse_period("week").unwrap(), BudgetPeriod::Weekly);
+    assert!(parse_period("fortnight").is_err());
+}
@@ -0,0 +1,196 @@
+//! Output format negotiation: format checks, reformatting and degradation
+
+mod common;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use hal9_core::{NeuronInterface, NeuronSignal};
+use hal9_server::chain_tracker::ChainTracker;
+use hal9_server::metrics::Metrics;
+use hal9_server::output_format::{request_format, FormatReport, FormatRequest, OutputFormat, OUTPUT_FORMAT_KEY};
+use hal9_server::{ManagedNeuron, MockClaude};
+use serde_json::{json, Value};
+
+use common::neuron_config;
+
+const PROSE: &str = "The answer is forty-two.";
+const ANSWER_JSON: &str = r#"{"answer": 42}"#;
+
+fn answer_schema() -> serde_json::Value {
+    json!({
+        "type": "object",
+        "properties": {"answer": {"type": "integer"}},
+        "required": ["answer"]
+    })
+}
+
+fn scripted(responses: &[&str]) -> Box<MockClaude> {
+    Box::new(MockClaude::scripted("L2", responses.iter().map(|r| r.to_string()).collect()))
+}
+
+/// A terminal neuron answering `responses`, reformatting with `reformats`
+fn neuron(responses: &[&str], reformats: &[&str], max_retries: u32, metrics: &Arc<Metrics>) -> ManagedNeuron {
+    let mut neuron = ManagedNeuron::new(neuron_config("neuron-l2", "L2", &[], Value::Null), scripted(responses)).unwrap();
+    neuron.set_metrics(metrics.clone());
+    neuron.set_reformatter(scripted(reformats), max_retries);
+    neuron
+}
+
+fn signal(format: OutputFormat, schema: Option<serde_json::Value>) -> NeuronSignal {
+    let mut signal = NeuronSignal::forward("neuron-l3", "neuron-l2", "L3", "L2", "answer".to_string());
+    request_format(&mut signal.metadata, format, schema.as_ref());
+    signal
+}
+
+#[test]
+fn test_format_checks() {
+    let markdown = FormatRequest::new(OutputFormat::Markdown, None).unwrap();
+    assert!(markdown.check("# Plan\n```rust\nfn a() {}\n```").is_ok());
+    let reason = markdown.check("# Plan\n```rust\nfn a() {}\n```\n\n```\nleft open").unwrap_err();
+    assert_eq!(reason, "code block opened at line 6 is never closed");
+
+    let json = FormatRequest::new(OutputFormat::Json, Some(answer_schema())).unwrap();
+    assert!(json.check(ANSWER_JSON).is_ok());
+    assert!(json.check(PROSE).unwrap_err().starts_with("output is not valid JSON"));
+    assert!(json.check(r#"{"answer": "many"}"#).unwrap_err().contains("does not match the JSON schema"));
+
+    // Any JSON passes without a schema
+    let any_json = FormatRequest::new(OutputFormat::Json, None).unwrap();
+    assert!(any_json.check("[1, 2]").is_ok());
+
+    let text = FormatRequest::new(OutputFormat::Text, None).unwrap();
+    assert!(text.check("Step one.\n- then two\n#hashtag").is_ok());
+    assert_eq!(text.check("Intro\n## Heading").unwrap_err(), "line 2 contains markdown: a heading");
+    assert!(text.check("very **bold**").is_err());
+    assert!(text.check("see [docs](https://example.com)").is_err());
+}
+
+#[test]
+fn test_invalid_requests_are_rejected() {
+    assert!(FormatRequest::new(OutputFormat::Text, Some(answer_schema())).is_err());
+    assert!(FormatRequest::new(OutputFormat::Json, Some(json!({"type": 12}))).is_err());
+
+    let metadata = HashMap::from([(OUTPUT_FORMAT_KEY.to_string(), "yaml".to_string())]);
+    let err = FormatRequest::from_metadata(&metadata).err().unwrap();
+    assert!(err.to_string().contains("Unknown output format 'yaml'"), "{}", err);
+
+    assert!(FormatRequest::from_metadata(&HashMap::new()).unwrap().is_none());
+}
+
+#[tokio::test]
+async fn test_json_output_passes() {
+    let metrics = Arc::new(Metrics::new());
+    let neuron = neuron(&[ANSWER_JSON], &[], 1, &metrics);
+    let signal = signal(OutputFormat::Json, Some(answer_schema()));
+
+    let response = neuron.process_signal(&signal).await.unwrap();
+    assert_eq!(response, ANSWER_JSON);
+    assert_eq!(neuron.take_format_report(&signal.signal_id), Some(FormatReport {
+        format: OutputFormat::Json,
+        degraded: false,
+        reason: None,
+        reformat_retries: 0,
+    }));
+    assert_eq!(metrics.snapshot().validation_results["json_schema:passed"], 1);
+}
+
+#[tokio::test]
+async fn test_json_output_is_reformatted() {
+    let metrics = Arc::new(Metrics::new());
+    let neuron = neuron(&[PROSE], &[ANSWER_JSON], 1, &metrics);
+    let signal = signal(OutputFormat::Json, Some(answer_schema()));
+
+    let response = neuron.process_signal(&signal).await.unwrap();
+    assert_eq!(response, ANSWER_JSON);
+
+    let report = neuron.take_format_report(&signal.signal_id).unwrap();
+    assert!(!report.degraded);
+    assert_eq!(report.reformat_retries, 1);
+    assert!(report.reason.unwrap().starts_with("json_schema: output is not valid JSON"));
+}
+
+#[tokio::test]
+async fn test_markdown_output_is_reformatted() {
+    let metrics = Arc::new(Metrics::new());
+    let fixed = "Done:\n```python\nx = 1\n```";
+    let neuron = neuron(&["Done:\n```python\nx = 1\n"], &[fixed], 2, &metrics);
+    let signal = signal(OutputFormat::Markdown, None);
+
+    assert_eq!(neuron.process_signal(&signal).await.unwrap(), fixed);
+    let report = neuron.take_format_report(&signal.signal_id).unwrap();
+    assert_eq!((report.format, report.degraded, report.reformat_retries), (OutputFormat::Markdown, false, 1));
+}
+
+#[tokio::test]
+async fn test_degraded_after_reformat_retries() {
+    let metrics = Arc::new(Metrics::new());
+    let neuron = neuron(&[PROSE], &["Still prose", "Prose again"], 2, &metrics);
+    let signal = signal(OutputFormat::Json, None);
+
+    // The original output comes back, flagged
+    assert_eq!(neuron.process_signal(&signal).await.unwrap(), PROSE);
+    let report = neuron.take_format_report(&signal.signal_id).unwrap();
+    assert!(report.degraded);
+    assert_eq!(report.reformat_retries, 2);
+    assert!(report.reason.unwrap().contains("not valid JSON"));
+
+    let snapshot = metrics.snapshot();
+    assert_eq!(snapshot.validation_results["json_schema:failed"], 3);
+    assert_eq!(snapshot.errors_by_type["format_degraded"], 1);
+}
+
+#[tokio::test]
+async fn test_text_output_degraded_without_reformatter() {
+    let metrics = Arc::new(Metrics::new());
+    let mut neuron = ManagedNeuron::new(neuron_config("neuron-l2", "L2", &[], Value::Null), scripted(&["## Result\nAll done"])).unwrap();
+    neuron.set_metrics(metrics.clone());
+    let signal = signal(OutputFormat::Text, None);
+
+    assert_eq!(neuron.process_signal(&signal).await.unwrap(), "## Result\nAll done");
+    let report = neuron.take_format_report(&signal.signal_id).unwrap();
+    assert!(report.degraded);
+    assert_eq!(report.reformat_retries, 0);
+    assert_eq!(report.reason.as_deref(), Some("plain_text: line 1 contains markdown: a heading"));
+}
+
+#[tokio::test]
+async fn test_forwarding_neurons_ignore_format() {
+    let metrics = Arc::new(Metrics::new());
+    let mut neuron = ManagedNeuron::new(neuron_config("neuron-l2", "L2", &["neuron-l1"], Value::Null), scripted(&[PROSE])).unwrap();
+    neuron.set_metrics(metrics.clone());
+    let signal = signal(OutputFormat::Json, None);
+
+    assert!(!neuron.is_terminal());
+    assert_eq!(neuron.process_signal(&signal).await.unwrap(), PROSE);
+    assert!(neuron.take_format_report(&signal.signal_id).is_none());
+}
+
+#[test]
+fn test_chain_result_carries_format() {
+    let tracker = ChainTracker::new();
+    let mut root = signal(OutputFormat::Json, None);
+    let chain_id = tracker.start(&mut root);
+
+    tracker.record_format(&root, FormatReport {
+        format: OutputFormat::Json,
+        degraded: true,
+        reason: Some("json_schema: output is not valid JSON".to_string()),
+        reformat_retries: 1,
+    });
+    tracker.record_step(&root, Ok(PROSE), 0);
+
+    let result = tracker.aggregate(&chain_id).unwrap();
+    assert_eq!(result.output_format, Some(OutputFormat::Json));
+    assert!(result.format_degraded);
+    assert_eq!(result.format_reason.as_deref(), Some("json_schema: output is not valid JSON"));
+    assert_eq!(tracker.get(&chain_id).unwrap().steps[0].format.as_ref().unwrap().reformat_retries, 1);
+
+    // Chains without a format request report none
+    let mut plain = NeuronSignal::forward("api", "neuron-l2", "API", "L2", "answer".to_string());
+    let plain_id = tracker.start(&mut plain);
+    tracker.record_step(&plain, Ok(PROSE), 0);
+    let result = tracker.aggregate(&plain_id).unwrap();
+    assert_eq!(result.output_format, None);
+    assert!(!result.format_degraded);
+}
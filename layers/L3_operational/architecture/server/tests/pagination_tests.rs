@@ -0,0 +1,203 @@
+//! Cursor pagination: complete walks under concurrent inserts, sort and filter grammar
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::thread;
+
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use parking_lot::Mutex;
+use proptest::prelude::*;
+use serde::Serialize;
+
+use hal9_server::pagination::{paginate, ListParams, Listable, SortKey, MAX_LIMIT};
+
+#[derive(Debug, Clone, Serialize)]
+struct Row {
+    id: String,
+    kind: String,
+    created_at: DateTime<Utc>,
+}
+
+impl Listable for Row {
+    const DEFAULT_SORT: &'static str = "created_at";
+    const SORT_FIELDS: &'static [&'static str] = &["created_at", "kind"];
+    const FILTER_FIELDS: &'static [&'static str] = &["kind"];
+
+    fn list_id(&self) -> String {
+        self.id.clone()
+    }
+
+    fn sort_key(&self, field: &str) -> SortKey {
+        match field {
+            "kind" => self.kind.as_str().into(),
+            _ => self.created_at.into(),
+        }
+    }
+}
+
+fn base_time() -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2026, 1, 1, 0, 0, 0).unwrap()
+}
+
+/// Rows whose timestamps collide often, to exercise id tie-breaking
+fn rows(offsets: &[i64]) -> Vec<Row> {
+    offsets.iter().enumerate().map(|(i, offset)| Row {
+        id: format!("row-{:05}", i),
+        kind: if i % 3 == 0 { "a" } else { "b" }.to_string(),
+        created_at: base_time() + Duration::seconds(*offset),
+    }).collect()
+}
+
+fn query(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+    pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+}
+
+/// Walk every page, calling `between` after each one; returns ids in order
+fn walk(table: &Mutex<Vec<Row>>, base: &[(&str, &str)], mut between: impl FnMut(usize)) -> Vec<String> {
+    let mut seen = Vec::new();
+    let mut cursor: Option<String> = None;
+    let mut page_no = 0;
+    loop {
+        let mut q = query(base);
+        if let Some(cursor) = &cursor {
+            q.insert("cursor".to_string(), cursor.clone());
+        }
+        let params = ListParams::parse::<Row>(&q, &[]).unwrap();
+        let snapshot = table.lock().clone();
+        let page = paginate(snapshot, &params).unwrap();
+        seen.extend(page.items.iter().map(|r| r.id.clone()));
+
+        between(page_no);
+        page_no += 1;
+        match page.next_cursor {
+            Some(next) => cursor = Some(next),
+            None => return seen,
+        }
+    }
+}
+
+fn assert_exactly_once(seen: &[String], initial: &[Row]) {
+    let unique: HashSet<_> = seen.iter().collect();
+    assert_eq!(unique.len(), seen.len(), "a row was returned twice");
+    for row in initial {
+        assert!(unique.contains(&row.id), "row {} was skipped", row.id);
+    }
+}
+
+proptest! {
+    #[test]
+    fn prop_walk_returns_every_row_once_under_inserts(
+        offsets in prop::collection::vec(0i64..20, 0..200),
+        limit in 1usize..25,
+        inserts_per_page in 0usize..5,
+        descending in any::<bool>(),
+    ) {
+        let initial = rows(&offsets);
+        let table = Mutex::new(initial.clone());
+        let limit = limit.to_string();
+        let sort = if descending { "-created_at" } else { "created_at" };
+
+        // Writers append rows stamped "now", which is never earlier than existing rows.
+        // They stop after a few pages: an ascending walk reaches every new row, so
+        // writers that keep up with the reader would keep it walking forever
+        let mut next_id = 0;
+        let seen = walk(&table, &[("limit", &limit), ("sort", sort)], |page_no| {
+            if page_no >= 20 {
+                return;
+            }
+            let mut table = table.lock();
+            for _ in 0..inserts_per_page {
+                table.push(Row {
+                    id: format!("new-{:05}", next_id),
+                    kind: "a".to_string(),
+                    created_at: base_time() + Duration::seconds(20 + next_id as i64 / 3),
+                });
+                next_id += 1;
+            }
+        });
+
+        assert_exactly_once(&seen, &initial);
+    }
+
+    #[test]
+    fn prop_filtered_walk_matches_filter(
+        offsets in prop::collection::vec(0i64..50, 0..150),
+        limit in 1usize..10,
+    ) {
+        let initial = rows(&offsets);
+        let table = Mutex::new(initial.clone());
+        let limit = limit.to_string();
+
+        let seen = walk(&table, &[("limit", &limit), ("kind", "a")], |_| {});
+        let expected: Vec<Row> = initial.into_iter().filter(|r| r.kind == "a").collect();
+        prop_assert_eq!(seen.len(), expected.len());
+        assert_exactly_once(&seen, &expected);
+    }
+}
+
+#[test]
+fn test_walk_with_concurrent_writer_threads() {
+    let initial = rows(&(0..500).map(|i| i / 7).collect::<Vec<_>>());
+    let table = Arc::new(Mutex::new(initial.clone()));
+
+    let writers: Vec<_> = (0..4).map(|w| {
+        let table = table.clone();
+        thread::spawn(move || {
+            for i in 0..200 {
+                table.lock().push(Row {
+                    id: format!("writer-{}-{:05}", w, i),
+                    kind: "b".to_string(),
+                    created_at: Utc::now(),
+                });
+                thread::yield_now();
+            }
+        })
+    }).collect();
+
+    let seen = walk(&table, &[("limit", "13")], |_| thread::yield_now());
+    for writer in writers {
+        writer.join().unwrap();
+    }
+
+    assert_exactly_once(&seen, &initial);
+}
+
+#[test]
+fn test_sort_and_filter_grammar() {
+    let table = rows(&[3, 1, 2]);
+
+    let params = ListParams::parse::<Row>(&query(&[("sort", "-created_at")]), &[]).unwrap();
+    let page = paginate(table.clone(), &params).unwrap();
+    let ids: Vec<_> = page.items.iter().map(|r| r.id.as_str()).collect();
+    assert_eq!(ids, vec!["row-00000", "row-00002", "row-00001"]);
+    assert_eq!(page.total_estimate, 3);
+    assert!(page.next_cursor.is_none());
+
+    // Comma-separated values match any of them
+    let params = ListParams::parse::<Row>(&query(&[("kind", "a,b")]), &[]).unwrap();
+    assert_eq!(paginate(table, &params).unwrap().items.len(), 3);
+
+    assert!(ListParams::parse::<Row>(&query(&[("sort", "id")]), &[]).is_err());
+    assert!(ListParams::parse::<Row>(&query(&[("owner", "x")]), &[]).is_err());
+    assert!(ListParams::parse::<Row>(&query(&[("owner", "x")]), &["owner"]).is_ok());
+    assert!(ListParams::parse::<Row>(&query(&[("limit", "many")]), &[]).is_err());
+}
+
+#[test]
+fn test_limit_is_capped() {
+    let params = ListParams::parse::<Row>(&query(&[("limit", "100000")]), &[]).unwrap();
+    assert_eq!(params.limit, MAX_LIMIT);
+}
+
+#[test]
+fn test_cursor_rejected_for_other_sort_or_garbage() {
+    let table = rows(&[1, 2, 3]);
+    let params = ListParams::parse::<Row>(&query(&[("limit", "1")]), &[]).unwrap();
+    let cursor = paginate(table.clone(), &params).unwrap().next_cursor.unwrap();
+
+    let other_sort = ListParams::parse::<Row>(&query(&[("sort", "kind"), ("cursor", &cursor)]), &[]).unwrap();
+    assert!(paginate(table.clone(), &other_sort).is_err());
+
+    let garbage = ListParams::parse::<Row>(&query(&[("cursor", "not-a-cursor")]), &[]).unwrap();
+    assert!(paginate(table, &garbage).is_err());
+}
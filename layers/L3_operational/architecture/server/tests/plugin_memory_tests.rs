@@ -0,0 +1,244 @@
+//! Memory namespaces stored by plugins: the reference LRU plugin's four
+//! operations, namespaced memory routed through it, and a failing plugin
+//! turning only its own namespace read-only
+#![cfg(feature = "plugins")]
+
+use std::sync::Arc;
+
+use uuid::Uuid;
+
+use hal9_core::config::{MemoryNamespacesConfig, NamespaceBackendConfig};
+use hal9_core::memory::{
+    MemoryBackend, MemoryBuilder, MemoryEntry, MemoryNamespace, MemorySearch, MemoryStore, NamespacedMemory,
+    SqliteMemoryStore,
+};
+use hal9_core::Error;
+use hal9_server::plugins::api::{PluginCapability, PluginContext, PluginMetadata, PluginRequirements, ResourceLimits};
+use hal9_server::plugins::{PluginBackend, RuntimeConfig, SecurityPolicy, WasmRuntime};
+
+const LRU_WAT: &str = include_str!("../plugins/examples/lru_memory.wat");
+
+/// Stores nothing and traps on every search
+const BROKEN_WAT: &str = r#"
+    (module
+      (memory (export "memory") 1)
+      (func (export "allocate") (param i32) (result i32) (i32.const 1024))
+      (func (export "memory_get") (param i32 i32) (result i64) (i64.const -1))
+      (func (export "memory_set") (param i32 i32 i32) (result i32) (i32.const 0))
+      (func (export "memory_delete") (param i32 i32) (result i32) (i32.const 0))
+      (func (export "memory_search") (param i32 i32 i32) (result i64) (unreachable)))
+"#;
+
+fn runtime() -> Arc<WasmRuntime> {
+    let config = RuntimeConfig {
+        fuel_per_call: 10_000_000,
+        enable_epoch_interruption: false,
+        enable_cache: false,
+        ..Default::default()
+    };
+    Arc::new(WasmRuntime::new(config).unwrap())
+}
+
+async fn load(runtime: &WasmRuntime, name: &str, wat: &str) {
+    let metadata = PluginMetadata {
+        id: Uuid::new_v4(),
+        name: name.to_string(),
+        version: "1.0.0".to_string(),
+        author: "Test".to_string(),
+        description: "Memory provider test plugin".to_string(),
+        license: "MIT".to_string(),
+        repository: None,
+        homepage: None,
+        capabilities: vec![PluginCapability::MemoryProvider {
+            storage_type: "lru".to_string(),
+            features: vec!["search".to_string()],
+        }],
+        requirements: PluginRequirements {
+            min_hal9_version: "0.1.0".to_string(),
+            max_memory_mb: 1,
+            required_permissions: vec![],
+            dependencies: vec![],
+        },
+    };
+    let context = PluginContext {
+        plugin_id: metadata.id,
+        config: serde_json::Value::Null,
+        permissions: vec![],
+        resource_limits: ResourceLimits {
+            max_memory_bytes: 1 << 20,
+            max_cpu_percent: 25.0,
+            max_execution_time_ms: 5000,
+            max_file_size_bytes: 0,
+            max_network_connections: 0,
+        },
+        host_version: "0.1.0".to_string(),
+    };
+    runtime
+        .load_plugin(name, wat.as_bytes(), metadata, context, &SecurityPolicy::default())
+        .await
+        .unwrap();
+}
+
+fn backend_config(plugin: &str) -> NamespaceBackendConfig {
+    NamespaceBackendConfig {
+        plugin: plugin.to_string(),
+        timeout_ms: 1000,
+        max_value_bytes: 64 * 1024,
+        max_response_bytes: 1024 * 1024,
+    }
+}
+
+fn layer(name: &str) -> MemoryNamespace {
+    MemoryNamespace::Layer(name.to_string())
+}
+
+fn entry(content: &str) -> MemoryEntry {
+    MemoryBuilder::new("coder".to_string(), "L3".to_string())
+        .with_namespace(&layer("L3"))
+        .with_content(content.to_string())
+        .build()
+}
+
+fn contents(entries: Vec<MemoryEntry>) -> Vec<String> {
+    entries.into_iter().map(|entry| entry.content).collect()
+}
+
+/// Layer namespaces L3 and L2 stored by the plugins `l3` and `l2` name
+async fn memory(runtime: &Arc<WasmRuntime>, l3: &str, l2: &str) -> (NamespacedMemory, Arc<dyn MemoryStore>) {
+    let store = SqliteMemoryStore::in_memory().await.unwrap();
+    store.initialize().await.unwrap();
+    let store: Arc<dyn MemoryStore> = Arc::new(store);
+
+    let mut config = MemoryNamespacesConfig::default();
+    config.policy.layer_write = true;
+    config.backends.insert("layer:L3".to_string(), backend_config(l3));
+    config.backends.insert("layer:L2".to_string(), backend_config(l2));
+    let mut memory = NamespacedMemory::new(store.clone(), config);
+    for (namespace, plugin) in [(layer("L3"), l3), (layer("L2"), l2)] {
+        if runtime.get_plugin_metadata(plugin).await.is_ok() {
+            let backend = PluginBackend::new(runtime.clone(), plugin, namespace.clone(), backend_config(plugin));
+            memory.set_backend(&namespace, Arc::new(backend));
+        }
+    }
+    (memory, store)
+}
+
+#[tokio::test]
+async fn test_lru_plugin_operations() {
+    let runtime = runtime();
+    load(&runtime, "lru", LRU_WAT).await;
+    let backend = PluginBackend::new(runtime, "lru", layer("L3"), backend_config("lru"));
+
+    let parser = entry("Ship the parser first");
+    let lexer = entry("Then the lexer");
+    backend.set(parser.clone()).await.unwrap();
+    backend.set(lexer.clone()).await.unwrap();
+    let found = backend.get(parser.id).await.unwrap().unwrap();
+    assert_eq!((found.id, found.content.as_str()), (parser.id, "Ship the parser first"));
+    assert!(backend.get(Uuid::new_v4()).await.unwrap().is_none());
+
+    // Most recently used first, and the get used the parser last
+    assert_eq!(contents(backend.search("", 0).await.unwrap()), ["Ship the parser first", "Then the lexer"]);
+    assert_eq!(contents(backend.search("lexer", 0).await.unwrap()), ["Then the lexer"]);
+    assert_eq!(backend.search("", 1).await.unwrap().len(), 1);
+    assert!(backend.search("compiler", 0).await.unwrap().is_empty());
+
+    // Setting an existing entry replaces it
+    let mut faster = lexer.clone();
+    faster.content = "Then a faster lexer".to_string();
+    backend.set(faster).await.unwrap();
+    assert_eq!(contents(backend.search("lexer", 0).await.unwrap()), ["Then a faster lexer"]);
+
+    assert!(backend.delete(parser.id).await.unwrap());
+    assert!(!backend.delete(parser.id).await.unwrap());
+    assert!(backend.get(parser.id).await.unwrap().is_none());
+
+    // With its 16 slots full, the least recently used entry is evicted
+    let notes: Vec<MemoryEntry> = (0..15).map(|i| entry(&format!("Note {}", i))).collect();
+    for note in &notes {
+        backend.set(note.clone()).await.unwrap();
+    }
+    backend.get(lexer.id).await.unwrap().unwrap();
+    backend.set(entry("One note too many")).await.unwrap();
+    assert!(backend.get(notes[0].id).await.unwrap().is_none());
+    assert!(backend.get(notes[1].id).await.unwrap().is_some());
+    assert!(backend.get(lexer.id).await.unwrap().is_some());
+
+    // Entries over the configured limit, or too large for the plugin, are
+    // refused without turning the namespace read-only
+    let err = backend.set(entry(&"x".repeat(70 * 1024))).await.unwrap_err();
+    assert!(matches!(err, Error::QuotaExceeded(_)), "{}", err);
+    let err = backend.set(entry(&"x".repeat(10 * 1024))).await.unwrap_err();
+    assert!(matches!(err, Error::QuotaExceeded(_)), "{}", err);
+    assert!(backend.failure().is_none());
+    backend.set(entry("Still writable")).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_namespace_routed_through_plugin() {
+    let runtime = runtime();
+    load(&runtime, "lru", LRU_WAT).await;
+    let (memory, store) = memory(&runtime, "lru", "missing").await;
+
+    memory.write("coder", "L3", &layer("L3"), entry("Ship the parser first")).await.unwrap();
+    memory.write("coder", "L3", &layer("L3"), entry("Then the lexer")).await.unwrap();
+    let private = MemoryNamespace::Private("coder".to_string());
+    memory.write("coder", "L3", &private, entry("My own notes")).await.unwrap();
+
+    let search = MemorySearch {
+        content_query: Some("parser".to_string()),
+        ..Default::default()
+    };
+    let found = memory.read("coder", "L3", &layer("L3"), search).await.unwrap();
+    assert_eq!(contents(found.clone()), ["Ship the parser first"]);
+    assert_eq!(found[0].namespace, "layer:L3");
+    let search = MemorySearch { limit: 1, ..Default::default() };
+    assert_eq!(memory.read("coder", "L3", &layer("L3"), search).await.unwrap().len(), 1);
+
+    // Only the private entry reached the store
+    let usage = store.namespace_usage(None).await.unwrap();
+    assert_eq!(usage.len(), 1);
+    assert_eq!(usage[0].namespace, "private:coder");
+    assert_eq!(contents(memory.read("coder", "L3", &private, MemorySearch::default()).await.unwrap()), ["My own notes"]);
+
+    // A namespace whose plugin never loaded is read-only
+    let err = memory.write("tester", "L2", &layer("L2"), entry("Lost")).await.unwrap_err();
+    assert!(matches!(err, Error::ReadOnly(_)), "{}", err);
+    assert!(err.to_string().contains("missing"), "{}", err);
+    assert!(memory.read("tester", "L2", &layer("L2"), MemorySearch::default()).await.is_err());
+}
+
+#[tokio::test]
+async fn test_failing_plugin_only_affects_its_namespace() {
+    let runtime = runtime();
+    load(&runtime, "lru", LRU_WAT).await;
+    load(&runtime, "broken", BROKEN_WAT).await;
+    let (memory, _store) = memory(&runtime, "lru", "broken").await;
+
+    // The broken plugin takes writes, then traps on the first search
+    memory.write("tester", "L2", &layer("L2"), entry("Accepted")).await.unwrap();
+    let err = memory.read("tester", "L2", &layer("L2"), MemorySearch::default()).await.unwrap_err();
+    assert!(matches!(err, Error::Storage(_)), "{}", err);
+    assert!(err.to_string().contains("layer:L2"), "{}", err);
+
+    // From then on its namespace refuses writes
+    let err = memory.write("tester", "L2", &layer("L2"), entry("Refused")).await.unwrap_err();
+    assert!(matches!(err, Error::ReadOnly(_)), "{}", err);
+    assert!(err.to_string().contains("memory_search"), "{}", err);
+
+    // Other namespaces carry on, plugin-backed or not
+    memory.write("coder", "L3", &layer("L3"), entry("Ship the parser first")).await.unwrap();
+    let found = memory.read("coder", "L3", &layer("L3"), MemorySearch::default()).await.unwrap();
+    assert_eq!(contents(found), ["Ship the parser first"]);
+    let private = MemoryNamespace::Private("coder".to_string());
+    memory.write("coder", "L3", &private, entry("My own notes")).await.unwrap();
+    assert_eq!(memory.read("coder", "L3", &private, MemorySearch::default()).await.unwrap().len(), 1);
+
+    // A backend whose plugin was terminated fails its reads as well
+    runtime.unload_plugin("lru").await.unwrap();
+    let err = memory.read("coder", "L3", &layer("L3"), MemorySearch::default()).await.unwrap_err();
+    assert!(err.to_string().contains("Plugin not found"), "{}", err);
+    let err = memory.write("coder", "L3", &layer("L3"), entry("Refused")).await.unwrap_err();
+    assert!(matches!(err, Error::ReadOnly(_)), "{}", err);
+    assert_eq!(memory.read("coder", "L3", &private, MemorySearch::default()).await.unwrap().len(), 1);
+}
@@ -0,0 +1,120 @@
+//! Built-in profiling: CPU profiles parse as pprof protobufs, and the poll
+//! monitor reports tasks blocking their worker thread
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use pprof::protos::{Message, Profile};
+
+use hal9_core::config::ProfilingConfig;
+use hal9_server::error::ServerError;
+use hal9_server::performance::{self, PollMonitor, Profiler};
+
+fn config(enabled: bool) -> ProfilingConfig {
+    ProfilingConfig {
+        enabled,
+        frequency_hz: 99,
+        max_profile_secs: 2,
+        poll_budget_ms: 5,
+        blocking_poll_ms: 50,
+        watchdog_interval_ms: 10,
+    }
+}
+
+/// Keep a thread busy for `duration`
+fn burn(duration: Duration) -> u64 {
+    let start = Instant::now();
+    let mut x = 0u64;
+    while start.elapsed() < duration {
+        for i in 0..10_000u64 {
+            x = x.wrapping_mul(31).wrapping_add(i);
+        }
+    }
+    x
+}
+
+// pprof samples the whole process, so every CPU profile is taken here, one
+// at a time
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_cpu_profile_under_load_is_a_bounded_pprof_profile() {
+    let profiler = Arc::new(Profiler::new(config(true)));
+    assert!(matches!(profiler.cpu_profile(Some(0)).await, Err(ServerError::InvalidInput(_))));
+    assert!(matches!(profiler.cpu_profile(Some(3)).await, Err(ServerError::InvalidInput(_))));
+
+    let load = std::thread::spawn(|| burn(Duration::from_millis(1500)));
+    let first = tokio::spawn({
+        let profiler = profiler.clone();
+        async move { profiler.cpu_profile(Some(1)).await }
+    });
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert!(profiler.status().profiling_cpu);
+    assert!(matches!(profiler.cpu_profile(Some(1)).await, Err(ServerError::LimitExceeded(_))));
+    let encoded = first.await.unwrap().unwrap();
+    load.join().unwrap();
+    assert!(!profiler.status().profiling_cpu);
+
+    // How many samples the load yields is up to the kernel's CPU timers;
+    // what is checked is that pprof tooling reads the profile
+    let profile = Profile::decode(encoded.as_slice()).unwrap();
+    let string = |index: i64| profile.string_table[index as usize].as_str();
+    let sample_types: Vec<_> = profile.sample_type.iter().map(|t| (string(t.ty), string(t.unit))).collect();
+    assert_eq!(sample_types, vec![("samples", "count"), ("cpu", "nanoseconds")]);
+    let period_type = profile.period_type.as_ref().unwrap();
+    assert_eq!((string(period_type.ty), string(period_type.unit)), ("cpu", "nanoseconds"));
+    assert_eq!(profile.period, 1_000_000_000 / 99);
+    assert!(profile.duration_nanos >= 1_000_000_000, "{}", profile.duration_nanos);
+    for sample in &profile.sample {
+        assert!(sample.location_id.iter().all(|id| profile.location.iter().any(|location| location.id == *id)));
+    }
+
+    // Switched off, nothing is sampled
+    let status = profiler.set_active(false, Some("alice"));
+    assert!(!status.active);
+    assert_eq!(status.last_switch.unwrap().by.as_deref(), Some("alice"));
+    assert!(matches!(profiler.cpu_profile(Some(1)).await, Err(ServerError::NotFound(_))));
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_watchdog_reports_a_task_blocking_its_worker() {
+    let monitor = PollMonitor::new(&config(true));
+
+    monitor.spawn(async { std::thread::sleep(Duration::from_millis(300)) }).await.unwrap();
+    monitor.spawn(async { tokio::time::sleep(Duration::from_millis(20)).await }).await.unwrap();
+
+    let stats = monitor.stats();
+    assert_eq!(stats.tasks, 0);
+    assert_eq!(stats.stalls, 1, "{:?}", stats.recent_stalls);
+    assert!(stats.over_budget >= 1);
+    assert!(stats.polls.count >= 3, "{}", stats.polls.count);
+
+    // Seen by the watchdog while the poll was still blocking, and not
+    // reported again when it returned
+    let stall = &stats.recent_stalls[0];
+    assert!(stall.in_progress);
+    assert!((50..300).contains(&stall.blocked_ms), "{}", stall.blocked_ms);
+    assert!(stall.location.contains("profiling_tests.rs"), "{}", stall.location);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_tasks_spawned_while_off_are_not_monitored() {
+    let monitor = PollMonitor::new(&config(false));
+    monitor.spawn(async { std::thread::sleep(Duration::from_millis(100)) }).await.unwrap();
+    let stats = monitor.stats();
+    assert_eq!((stats.polls.count, stats.stalls), (0, 0));
+
+    monitor.set_active(true);
+    let blocking = monitor.spawn(async { std::thread::sleep(Duration::from_millis(100)) });
+    assert_eq!(monitor.stats().tasks, 1);
+    blocking.await.unwrap();
+    assert_eq!(monitor.stats().stalls, 1);
+}
+
+#[test]
+fn test_validate_rejects_unbounded_profiling() {
+    assert!(performance::validate(&ProfilingConfig::default()).is_ok());
+    assert!(performance::validate(&ProfilingConfig { frequency_hz: 0, ..config(true) }).is_err());
+    assert!(performance::validate(&ProfilingConfig { frequency_hz: 5000, ..config(true) }).is_err());
+    assert!(performance::validate(&ProfilingConfig { max_profile_secs: 3600, ..config(true) }).is_err());
+    assert!(performance::validate(&ProfilingConfig { blocking_poll_ms: 1, ..config(true) }).is_err());
+    assert!(performance::validate(&ProfilingConfig { watchdog_interval_ms: 0, ..config(true) }).is_err());
+}
@@ -0,0 +1,341 @@
+//! Prompt composition accounting: sections counted against known prompts,
+//! scaled to reported usage, carried in signal metadata and aggregated into
+//! the per-layer report
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+use chrono::{DateTime, Duration, TimeZone, Utc};
+
+use hal9_core::config::{get_system_prompt, BudgetPeriod, ModelsConfig, PromptCompositionConfig};
+use hal9_core::{NeuronConfig, NeuronInterface, NeuronSignal};
+use hal9_server::claude::TokenUsage;
+use hal9_server::model_registry::ModelRegistry;
+use hal9_server::prompt_composition::{
+    self, split_adjusted, ComposedPrompt, PromptAccounting, PromptComposition, PromptLedger, PromptSection,
+    SectionTotals, Tokenizer, PROMPT_SECTIONS_KEY, PROMPT_TOKENIZER_KEY,
+};
+use hal9_server::{ManagedNeuron, MockClaude};
+
+const SYSTEM: &str = "You are an implementation AI neuron in a hierarchical neural network.";
+const TRAILER: &str = "\nYour response should include FORWARD_TO: <target_neurons> and CONTENT: <your_analysis>";
+const USER: &str = "From: user\nLayer: input\nStrength: 1\nContent: Summarise the Q3 report\nFeatures: {}\n";
+const MEMORY: &str = "\n\nRECENT TASKS:\n- [m1] Summarised the Q2 report\n";
+const CORRECTION: &str = "Always cite the source figures.";
+
+fn approx(actual: f64, expected: f64) {
+    assert!((actual - expected).abs() < 1e-9, "{} != {}", actual, expected);
+}
+
+/// A forward prompt with a learned correction and one recalled memory
+fn known_prompt() -> ComposedPrompt {
+    let mut prompt = ComposedPrompt::new();
+    prompt.push(PromptSection::LayerInstructions, "FORWARD_SIGNAL\n");
+    prompt.push(PromptSection::Corrections, CORRECTION);
+    prompt.push(PromptSection::UserContent, USER);
+    prompt.push(PromptSection::Memory, MEMORY);
+    prompt.push(PromptSection::Memory, "");
+    prompt.push(PromptSection::LayerInstructions, TRAILER);
+    prompt
+}
+
+fn composition(tokens: &[(PromptSection, u64)]) -> PromptComposition {
+    PromptComposition { tokenizer: Tokenizer::Cl100kBase, tokens: tokens.iter().copied().collect(), calibrated: true }
+}
+
+fn config() -> PromptCompositionConfig {
+    PromptCompositionConfig { enabled: true, static_share_threshold: 0.5, cache_read_ratio: 0.1 }
+}
+
+fn now() -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2024, 5, 20, 12, 0, 0).unwrap()
+}
+
+#[test]
+fn test_sections_counted_against_known_prompts() {
+    let prompt = known_prompt();
+    assert_eq!(prompt.parts().len(), 5);
+    assert_eq!(prompt.text(), format!("FORWARD_SIGNAL\n{}{}{}{}", CORRECTION, USER, MEMORY, TRAILER));
+
+    let counted = prompt.count(SYSTEM, Tokenizer::Cl100kBase);
+    let expected = BTreeMap::from([
+        (PromptSection::System, 12),
+        (PromptSection::LayerInstructions, 4 + 19),
+        (PromptSection::Memory, 19),
+        (PromptSection::Corrections, 6),
+        (PromptSection::UserContent, 26),
+    ]);
+    assert_eq!(counted.tokens, expected);
+    assert_eq!(counted.total(), 86);
+    assert_eq!(counted.static_tokens(), 35);
+    assert!(!counted.calibrated);
+
+    // The trailer's markup splits differently in the newer vocabulary
+    let counted = prompt.count(SYSTEM, Tokenizer::O200kBase);
+    assert_eq!(counted.tokens[&PromptSection::LayerInstructions], 4 + 21);
+    assert_eq!(counted.total(), 88);
+
+    // Sections missing from a prompt are left out
+    let mut bare = ComposedPrompt::new();
+    bare.push(PromptSection::UserContent, "hello world");
+    let counted = bare.count("", Tokenizer::Cl100kBase);
+    assert_eq!(counted.tokens, BTreeMap::from([(PromptSection::UserContent, 2)]));
+}
+
+#[test]
+fn test_tokenizer_follows_model() {
+    assert_eq!(Tokenizer::for_model("claude-3-sonnet-20240229"), Tokenizer::Cl100kBase);
+    assert_eq!(Tokenizer::for_model("local/llama-3-8b"), Tokenizer::Cl100kBase);
+    assert_eq!(Tokenizer::for_model("gpt-4"), Tokenizer::Cl100kBase);
+    assert_eq!(Tokenizer::for_model("gpt-4o-mini"), Tokenizer::O200kBase);
+    assert_eq!(Tokenizer::for_model("o3-mini"), Tokenizer::O200kBase);
+    assert_eq!(Tokenizer::Cl100kBase.count("hello world"), 2);
+    assert_eq!(Tokenizer::O200kBase.count("hello world"), 2);
+}
+
+#[test]
+fn test_learned_adjustments_split_from_base_prompt() {
+    let base = "You are a helper. Be brief.";
+    assert_eq!(
+        split_adjusted(base, "You are a helper. Cite sources. Be brief."),
+        ("You are a helper. ", "Cite sources. ", "Be brief.")
+    );
+    assert_eq!(split_adjusted(base, base), (base, "", ""));
+    assert_eq!(split_adjusted("abc", "abc\nAlso check units"), ("abc", "\nAlso check units", ""));
+    assert_eq!(split_adjusted("abc", "ab"), ("ab", "", ""));
+    assert_eq!(split_adjusted("café", "cafè"), ("caf", "è", ""));
+}
+
+#[test]
+fn test_calibration_adds_up_to_reported_tokens() {
+    let mut counted = known_prompt().count(SYSTEM, Tokenizer::Cl100kBase);
+    counted.calibrate(172);
+    assert!(counted.calibrated);
+    assert_eq!(counted.total(), 172);
+    assert_eq!(counted.tokens[&PromptSection::System], 24);
+    assert_eq!(counted.tokens[&PromptSection::UserContent], 52);
+
+    // Leftovers of rounding go to the earlier sections on a tie
+    let mut even = composition(&[(PromptSection::System, 1), (PromptSection::LayerInstructions, 1), (PromptSection::Memory, 1)]);
+    even.calibrate(5);
+    assert_eq!(even.tokens.values().copied().collect::<Vec<_>>(), vec![2, 2, 1]);
+
+    // And otherwise to the largest fractions: 10 * 5/8 = 6.25, 10 * 3/8 = 3.75
+    let mut uneven = composition(&[(PromptSection::System, 5), (PromptSection::UserContent, 3)]);
+    uneven.calibrate(10);
+    assert_eq!(uneven.tokens[&PromptSection::System], 6);
+    assert_eq!(uneven.tokens[&PromptSection::UserContent], 4);
+}
+
+#[test]
+fn test_counts_round_trip_through_signal_metadata() {
+    let counted = known_prompt().count(SYSTEM, Tokenizer::Cl100kBase);
+    let mut metadata = HashMap::from([("note".to_string(), "kept".to_string())]);
+    counted.apply_to(&mut metadata);
+    assert_eq!(
+        metadata[PROMPT_SECTIONS_KEY],
+        "system=12,layer_instructions=23,memory=19,corrections=6,user_content=26"
+    );
+    assert_eq!(metadata[PROMPT_TOKENIZER_KEY], "cl100k_base");
+    assert_eq!(metadata["note"], "kept");
+
+    let parsed = PromptComposition::from_metadata(&metadata).unwrap();
+    assert_eq!(parsed.tokens, counted.tokens);
+    assert_eq!(parsed.tokenizer, Tokenizer::Cl100kBase);
+
+    metadata.insert(PROMPT_SECTIONS_KEY.to_string(), "system=12,greeting=3".to_string());
+    assert!(PromptComposition::from_metadata(&metadata).is_none());
+    assert!(PromptComposition::from_metadata(&HashMap::new()).is_none());
+}
+
+#[test]
+fn test_accounting_calibrates_to_provider_usage_only() {
+    let models = Arc::new(ModelRegistry::new(&ModelsConfig::default()));
+    let accounting = PromptAccounting::new(config(), models.clone(), "claude-3-opus-20240229");
+    let prompt = known_prompt();
+
+    // Usage a provider reported for a model
+    let usage = TokenUsage { prompt_tokens: 172, model: Some("claude-3-haiku-20240307".to_string()), ..Default::default() };
+    let recorded = accounting.record("implementer-1", "L2", &prompt, SYSTEM, Some(&usage)).unwrap();
+    assert!(recorded.calibrated);
+    assert_eq!(recorded.total(), 172);
+
+    // Mock usage names no model and is not scaled to
+    let mock = TokenUsage { prompt_tokens: 100, ..Default::default() };
+    let recorded = accounting.record("implementer-1", "L2", &prompt, SYSTEM, Some(&mock)).unwrap();
+    assert!(!recorded.calibrated);
+    assert_eq!(recorded.total(), 86);
+
+    let gpt = TokenUsage { prompt_tokens: 88, model: Some("gpt-4o".to_string()), ..Default::default() };
+    let recorded = accounting.record("implementer-1", "L2", &prompt, SYSTEM, Some(&gpt)).unwrap();
+    assert_eq!(recorded.tokenizer, Tokenizer::O200kBase);
+    assert_eq!(recorded.total(), 88);
+
+    // Haiku, then opus, then the default price of unknown models
+    let report = accounting.report(BudgetPeriod::Daily);
+    assert_eq!(report.prompts, 3);
+    approx(report.cost_usd, 172.0 / 1000.0 * 0.00025 + 86.0 / 1000.0 * 0.015 + 88.0 / 1000.0 * 0.003);
+
+    let disabled = PromptAccounting::new(PromptCompositionConfig { enabled: false, ..config() }, models, "claude-3-opus-20240229");
+    assert!(disabled.record("implementer-1", "L2", &prompt, SYSTEM, None).is_none());
+    assert_eq!(disabled.report(BudgetPeriod::Daily).prompts, 0);
+}
+
+#[tokio::test]
+async fn test_neuron_counts_its_prompts_into_the_signals_it_produces() {
+    let neuron_config = NeuronConfig {
+        id: "neuron-l2".to_string(),
+        layer: "L2".to_string(),
+        claude_command: "claude".to_string(),
+        system_prompt: Some("Implement the design you are given.".to_string()),
+        forward_connections: vec!["neuron-l1".to_string()],
+        backward_connections: vec![],
+        settings: HashMap::new(),
+    };
+    let claude = MockClaude::scripted("L2", vec!["FORWARD_TO: neuron-l1\nCONTENT: done".to_string()]);
+    let mut neuron = ManagedNeuron::new(neuron_config, Box::new(claude)).unwrap();
+    let models = Arc::new(ModelRegistry::new(&ModelsConfig::default()));
+    let accounting = Arc::new(PromptAccounting::new(config(), models, "claude-3-sonnet-20240229"));
+    neuron.set_prompt_accounting(accounting.clone());
+
+    let parent = NeuronSignal::forward("neuron-l3", "neuron-l2", "L3", "L2", "Summarise the Q3 report".into());
+    let response = neuron.process_signal(&parent).await.unwrap();
+    let children = neuron.parse_response(&response, &parent);
+    assert_eq!(children.len(), 1);
+
+    let counted = PromptComposition::from_metadata(&children[0].metadata).unwrap();
+    let activation = &parent.payload.activation;
+    let user = format!(
+        "From: {}\nLayer: {}\nStrength: {}\nContent: {}\nFeatures: {:?}\n",
+        parent.from_neuron, parent.layer_from, activation.strength, activation.content, activation.features,
+    );
+    assert_eq!(counted.tokenizer, Tokenizer::Cl100kBase);
+    assert_eq!(counted.tokens[&PromptSection::System], Tokenizer::Cl100kBase.count(&get_system_prompt("L2")));
+    assert_eq!(counted.tokens[&PromptSection::UserContent], Tokenizer::Cl100kBase.count(&user));
+    assert!(counted.tokens[&PromptSection::LayerInstructions] > Tokenizer::Cl100kBase.count("Implement the design you are given."));
+    assert!(!counted.tokens.contains_key(&PromptSection::Memory));
+    assert!(!counted.tokens.contains_key(&PromptSection::Corrections));
+
+    // The counts describe the call that produced the signal, not its children
+    let grandchildren = neuron.parse_response(&response, &children[0]);
+    assert!(PromptComposition::from_metadata(&grandchildren[0].metadata).is_none());
+
+    let report = accounting.report(BudgetPeriod::Daily);
+    assert_eq!(report.prompts, 1);
+    assert_eq!(report.layers[0].layer, "L2");
+    approx(report.layers[0].average_tokens, counted.total() as f64);
+}
+
+#[test]
+fn test_report_averages_sections_and_projects_savings() {
+    let implementer = composition(&[
+        (PromptSection::System, 400),
+        (PromptSection::LayerInstructions, 120),
+        (PromptSection::Memory, 60),
+        (PromptSection::Corrections, 20),
+        (PromptSection::UserContent, 100),
+    ]);
+    let architect = composition(&[
+        (PromptSection::System, 100),
+        (PromptSection::LayerInstructions, 50),
+        (PromptSection::UserContent, 350),
+    ]);
+    let mut ledger = PromptLedger::default();
+    ledger.record("L2", "implementer-1", SectionTotals::of(&implementer, 0.003), now());
+    ledger.record("L2", "implementer-1", SectionTotals::of(&implementer, 0.003), now() - Duration::hours(3));
+    ledger.record("L3", "architect-1", SectionTotals::of(&architect, 0.015), now());
+
+    let report = ledger.report(BudgetPeriod::Monthly, now(), &config());
+    assert_eq!(report.period_start, Utc.with_ymd_and_hms(2024, 5, 1, 0, 0, 0).unwrap());
+    assert_eq!(report.period_end, Utc.with_ymd_and_hms(2024, 6, 1, 0, 0, 0).unwrap());
+    assert_eq!(report.prompts, 3);
+    assert_eq!(report.layers.len(), 2);
+
+    let l2 = &report.layers[0];
+    assert_eq!(l2.layer, "L2");
+    assert_eq!(l2.prompts, 2);
+    approx(l2.average_tokens, 700.0);
+    approx(l2.static_share, 520.0 / 700.0);
+    approx(l2.cost_usd, 0.0042);
+    approx(l2.caching_savings_usd, 0.52 * 2.0 * 0.003 * 0.9);
+    let sections: Vec<PromptSection> = l2.sections.iter().map(|section| section.section).collect();
+    assert_eq!(sections, PromptSection::ALL.to_vec());
+    let system = &l2.sections[0];
+    assert!(system.static_section);
+    approx(system.average_tokens, 400.0);
+    approx(system.share, 400.0 / 700.0);
+    approx(system.cost_usd, 0.0024);
+    approx(system.caching_savings_usd, 0.00216);
+    let memory = &l2.sections[2];
+    assert!(!memory.static_section);
+    approx(memory.cost_usd, 0.00036);
+    approx(memory.caching_savings_usd, 0.0);
+
+    let l3 = &report.layers[1];
+    approx(l3.average_tokens, 500.0);
+    approx(l3.static_share, 0.3);
+    approx(l3.sections[2].average_tokens, 0.0);
+    approx(l3.cost_usd, 0.0075);
+    approx(l3.caching_savings_usd, 0.15 * 0.015 * 0.9);
+
+    approx(report.cost_usd, 0.0042 + 0.0075);
+    approx(report.caching_savings_usd, l2.caching_savings_usd + l3.caching_savings_usd);
+
+    // Only the implementer's static sections are over half of its prompts
+    assert_eq!(report.flagged_neurons.len(), 1);
+    let flagged = &report.flagged_neurons[0];
+    assert_eq!((flagged.neuron_id.as_str(), flagged.layer.as_str(), flagged.prompts), ("implementer-1", "L2", 2));
+    approx(flagged.average_tokens, 700.0);
+    approx(flagged.average_static_tokens, 520.0);
+
+    // Highest share first
+    let lenient = PromptCompositionConfig { static_share_threshold: 0.2, ..config() };
+    let flagged: Vec<String> = ledger.report(BudgetPeriod::Monthly, now(), &lenient)
+        .flagged_neurons
+        .into_iter()
+        .map(|neuron| neuron.neuron_id)
+        .collect();
+    assert_eq!(flagged, vec!["implementer-1".to_string(), "architect-1".to_string()]);
+}
+
+#[test]
+fn test_report_covers_its_period_only() {
+    let prompt = composition(&[(PromptSection::System, 100), (PromptSection::UserContent, 100)]);
+    let mut ledger = PromptLedger::default();
+    // 2024-05-20 is a Monday
+    ledger.record("L2", "implementer-1", SectionTotals::of(&prompt, 0.003), now());
+    ledger.record("L2", "implementer-1", SectionTotals::of(&prompt, 0.003), now() - Duration::days(1));
+    ledger.record("L2", "implementer-1", SectionTotals::of(&prompt, 0.003), now() - Duration::days(25));
+
+    assert_eq!(ledger.report(BudgetPeriod::Daily, now(), &config()).prompts, 1);
+    assert_eq!(ledger.report(BudgetPeriod::Weekly, now(), &config()).prompts, 1);
+    assert_eq!(ledger.report(BudgetPeriod::Monthly, now(), &config()).prompts, 2);
+    let april = ledger.report(BudgetPeriod::Monthly, now() - Duration::days(25), &config());
+    assert_eq!(april.prompts, 1);
+
+    // Days past retention are dropped as new ones come in
+    let later = now() + Duration::days(prompt_composition::RETENTION_DAYS);
+    ledger.record("L2", "implementer-1", SectionTotals::of(&prompt, 0.003), later);
+    assert_eq!(ledger.report(BudgetPeriod::Monthly, now() - Duration::days(25), &config()).prompts, 0);
+    assert_eq!(ledger.report(BudgetPeriod::Monthly, now(), &config()).prompts, 1);
+
+    let empty = PromptLedger::default().report(BudgetPeriod::Monthly, now(), &config());
+    assert!(empty.layers.is_empty());
+    assert!(empty.flagged_neurons.is_empty());
+    approx(empty.cost_usd, 0.0);
+}
+
+#[test]
+fn test_validate_rejects_shares_out_of_range() {
+    assert!(prompt_composition::validate(&PromptCompositionConfig::default()).is_ok());
+    let invalid = [
+        PromptCompositionConfig { static_share_threshold: 1.5, ..config() },
+        PromptCompositionConfig { static_share_threshold: -0.1, ..config() },
+        PromptCompositionConfig { cache_read_ratio: 2.0, ..config() },
+        PromptCompositionConfig { cache_read_ratio: f64::NAN, ..config() },
+    ];
+    for config in invalid {
+        let error = prompt_composition::validate(&config).unwrap_err().to_string();
+        assert!(error.contains("Invalid prompt_composition config"), "{}", error);
+    }
+}
@@ -0,0 +1,178 @@
+//! Quality benchmarks: graders scoring outputs, runs over the API, and the
+//! gate refusing a prompt change that degrades its neuron
+
+mod common;
+
+use std::sync::Arc;
+
+use axum::http::StatusCode;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use hal9_core::config::ClaudeConfig;
+use hal9_core::ServerConfig;
+use hal9_server::api::create_api_router;
+use hal9_server::claude::{ClaudeInterface, MockClaude};
+use hal9_server::quality::{self, Grader};
+use hal9_server::server::HAL9Server;
+use hal9_server::topology::PatchOp;
+
+use common::call;
+
+/// Outputs with the score their grader should give, `null` where grading
+/// should fail
+const GRADER_FIXTURES: &str = include_str!("fixtures/quality_graders.json");
+
+#[derive(Deserialize)]
+struct GraderCase {
+    name: String,
+    grader: Grader,
+    output: String,
+    judge_reply: Option<String>,
+    score: Option<f64>,
+}
+
+const TERSE: &str = "Answer with the city name only.";
+const DEGRADED: &str = "Answer as an essay.";
+
+fn config(database: &str) -> ServerConfig {
+    common::mock_config(json!({
+        "server_id": "quality-test",
+        "neurons": [
+            {"id": "geographer", "layer": "L2", "system_prompt": TERSE, "forward_connections": [], "backward_connections": []},
+        ],
+        "claude": {
+            "mock_responses": {
+                "L2": [
+                    {"trigger": "Answer with the city name only", "response": "Paris", "delay_ms": 0},
+                    {"trigger": "Answer as an essay", "response": "Historians still argue; Lyon and Paris both have claims.", "delay_ms": 0},
+                ],
+            },
+        },
+        "memory": {"enabled": false},
+        "database": {"url": format!("sqlite:{}?mode=rwc", database)},
+        "quality": {"enabled": true, "gate": true, "run_on_change": false},
+    }))
+}
+
+#[tokio::test]
+async fn test_graders_score_fixtures() {
+    let cases: Vec<GraderCase> = serde_json::from_str(GRADER_FIXTURES).unwrap();
+    for case in cases {
+        assert_eq!(case.grader.check(), Ok(()), "{}", case.name);
+        let reply = case.judge_reply.unwrap_or_default();
+        let claude: ClaudeConfig = serde_json::from_value(json!({
+            "mode": "mock",
+            "mock_responses": {"judge": [{"trigger": "default", "response": reply, "delay_ms": 0}]},
+        }))
+        .unwrap();
+        let judge: Box<dyn ClaudeInterface> = Box::new(MockClaude::new("judge", &claude));
+
+        let graded = case.grader.grade("What is the capital of France?", &case.output, Some(judge.as_ref())).await;
+        match case.score {
+            Some(score) => {
+                let grade = graded.unwrap_or_else(|e| panic!("{}: {}", case.name, e));
+                assert!((grade.score - score).abs() < 1e-9, "{}: scored {}", case.name, grade.score);
+            }
+            None => assert!(graded.is_err(), "{}: {:?}", case.name, graded),
+        }
+    }
+
+    let broken: Grader = serde_json::from_value(json!({"type": "regex", "patterns": ["(unclosed"]})).unwrap();
+    assert!(broken.check().is_err());
+    let empty: Grader = serde_json::from_value(json!({"type": "regex"})).unwrap();
+    assert!(empty.check().is_err());
+}
+
+#[tokio::test]
+async fn test_gate_refuses_a_degraded_prompt() {
+    let dir = tempfile::tempdir().unwrap();
+    let database = dir.path().join("hal9.db");
+    let server = Arc::new(HAL9Server::new(config(database.to_str().unwrap())));
+    server.start().await.unwrap();
+    let app = create_api_router(server.clone());
+
+    // Five phrasings of one question; the terse prompt gets every one right
+    let questions = [
+        "What is the capital of France?",
+        "Which city is the French capital?",
+        "Where does the French government sit?",
+        "Name the capital city of France.",
+        "France's capital is which city?",
+    ];
+    for (i, question) in questions.iter().enumerate() {
+        let benchmark = json!({
+            "name": format!("capital-{}", i),
+            "neuron": "geographer",
+            "input": question,
+            "grader": {"type": "exact", "expected": "Paris"},
+        });
+        let (status, body) = call(&app, "POST", "/api/v1/quality/benchmarks", Some(benchmark)).await;
+        assert_eq!(status, StatusCode::CREATED, "{}", body);
+    }
+    let unknown = json!({"name": "x", "neuron": "ghost", "input": "?", "grader": {"type": "exact", "expected": "Paris"}});
+    let (status, _) = call(&app, "POST", "/api/v1/quality/benchmarks", Some(unknown)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, body) = call(&app, "POST", "/api/v1/quality/runs", None).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    let baseline = &body["data"];
+    assert_eq!(baseline["mean_score"], 1.0, "{}", body);
+    assert_eq!(baseline["scores"].as_array().unwrap().len(), 5);
+
+    // The essay prompt misses every benchmark, so the gate refuses it
+    let set_prompt = |prompt: &str| vec![PatchOp::SetPrompt { id: "geographer".to_string(), system_prompt: Some(prompt.to_string()) }];
+    let proposal = server.propose_topology_patch(set_prompt(DEGRADED)).unwrap();
+    let (status, body) =
+        call(&app, "POST", "/api/v1/admin/topology/patch", Some(json!({"token": proposal.token}))).await;
+    assert_eq!(status, StatusCode::FORBIDDEN, "{}", body);
+    let error = body["error"].as_str().unwrap();
+    assert!(error.contains("Quality gate failed: L2 -1.00"), "{}", error);
+    assert_eq!(server.registry().get("geographer").unwrap().config.system_prompt.as_deref(), Some(TERSE));
+
+    // The candidate was scored against the stored baseline, not a new one
+    let (_, body) = call(&app, "GET", "/api/v1/quality/runs?candidate=true", None).await;
+    let candidates = body["data"]["items"].as_array().unwrap();
+    assert_eq!(candidates.len(), 1, "{}", body);
+    assert_eq!((candidates[0]["triggered_by"].as_str(), candidates[0]["mean_score"].as_f64()), (Some("gate"), Some(0.0)));
+    let (_, body) = call(&app, "GET", "/api/v1/quality/runs?candidate=false", None).await;
+    assert_eq!(body["data"]["items"].as_array().unwrap().len(), 1);
+    let candidate_id = candidates[0]["id"].as_str().unwrap();
+    let (_, body) = call(&app, "GET", &format!("/api/v1/quality/runs/{}", candidate_id), None).await;
+    assert!(body["data"]["scores"][0]["detail"].as_str().unwrap().contains("Expected \"Paris\""), "{}", body);
+
+    // A rewording that keeps the answers is promoted
+    let proposal = server.propose_topology_patch(set_prompt("Answer with the city name only, and nothing else.")).unwrap();
+    let applied = server.apply_topology_patch(&proposal.token, Some("ops")).await.unwrap();
+    assert_eq!(applied.version, 1);
+
+    let (status, _) = call(&app, "POST", "/api/v1/quality/runs", None).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, body) = call(&app, "GET", "/api/v1/quality/trends", None).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    let trends = &body["data"];
+    assert_ne!(trends["latest"]["config_hash"], trends["baseline"]["config_hash"]);
+    assert_eq!(trends["layers"][0]["layer"], "L2");
+    assert_eq!(trends["layers"][0]["benchmarks"], 5);
+    assert_eq!(trends["layers"][0]["delta"], 0.0);
+    assert_eq!(trends["regressed"], false);
+
+    server.shutdown().await.unwrap();
+}
+
+#[test]
+fn test_quality_config_is_validated() {
+    let parse = |quality: Value, url: Option<&str>| {
+        let config = common::mock_config(json!({
+            "server_id": "quality-test",
+            "quality": quality,
+            "database": {"url": url},
+        }));
+        quality::validate(&config.quality, &config.database)
+    };
+    assert!(parse(json!({"enabled": true}), Some("sqlite::memory:")).is_ok());
+    assert!(parse(json!({"enabled": true}), None).is_err());
+    assert!(parse(json!({"gate": true}), None).is_err());
+    assert!(parse(json!({"enabled": true, "significance": 0.0}), Some("sqlite::memory:")).is_err());
+    assert!(parse(json!({"enabled": true, "min_drop": 1.5}), Some("sqlite::memory:")).is_err());
+}
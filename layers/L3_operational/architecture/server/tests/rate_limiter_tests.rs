@@ -20,6 +20,7 @@ async fn test_rate_limiter_integration() {
         window_duration: Duration::from_secs(1),
         enabled: true,
         burst_size: 0,
+        ..Default::default()
     };
     
     let rate_limiter = Arc::new(RateLimiter::new(config));
@@ -66,6 +67,7 @@ async fn test_rate_limiter_different_ips() {
         window_duration: Duration::from_secs(1),
         enabled: true,
         burst_size: 0,
+        ..Default::default()
     };
     
     let rate_limiter = Arc::new(RateLimiter::new(config));
@@ -108,6 +110,7 @@ async fn test_rate_limiter_disabled() {
         window_duration: Duration::from_secs(1),
         enabled: false,
         burst_size: 0,
+        ..Default::default()
     };
     
     let rate_limiter = Arc::new(RateLimiter::new(config));
@@ -140,6 +143,7 @@ async fn test_rate_limiter_token_refill() {
         window_duration: Duration::from_millis(500),
         enabled: true,
         burst_size: 0,
+        ..Default::default()
     };
     
     let rate_limiter = Arc::new(RateLimiter::new(config));
@@ -0,0 +1,256 @@
+//! Read-only mode: refused writes, reads that keep working, and memory
+//! writes held or skipped until the mode ends
+
+mod common;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::{
+    http::{header, Method, StatusCode},
+    Router,
+};
+use serde_json::{json, Value};
+
+use hal9_api_types::{ErrorCode, ErrorResponse};
+use hal9_core::config::{MemoryNamespacesConfig, MemoryWritePolicy, ReadOnlyConfig};
+use hal9_core::memory::{MemoryBuilder, MemoryNamespace, MemoryStore, NamespacedMemory, SqliteMemoryStore};
+use hal9_core::{NeuronSignal, ServerConfig};
+use hal9_server::api::create_api_router;
+use hal9_server::chain_tracker::ChainStatus;
+use hal9_server::read_only::{is_mutating, MemoryWrite, MemoryWriteAdmission, ReadOnlyGate};
+use hal9_server::server::HAL9Server;
+
+/// Every route that changes state, with the method it is served on
+const MUTATING_ROUTES: &[(&str, &str)] = &[
+    ("POST", "/api/v1/signal"),
+    ("POST", "/api/v1/signals/batch"),
+    ("POST", "/api/v1/chains/chain-1/replay"),
+    ("POST", "/api/v1/memory/import"),
+    ("POST", "/api/v1/memory/pending/entry-1/approve"),
+    ("POST", "/api/v1/memory/pending/entry-1/reject"),
+    ("POST", "/api/v1/dead-letters/signal-1/retry"),
+    ("POST", "/api/v1/intelligence/create"),
+    ("POST", "/api/v1/goals"),
+    ("POST", "/api/v1/webhooks"),
+    ("DELETE", "/api/v1/webhooks/hook-1"),
+    ("POST", "/api/v1/webhooks/hook-1/test"),
+    ("POST", "/api/v1/ingest/webhooks/github"),
+    ("POST", "/api/v1/quality/benchmarks"),
+    ("DELETE", "/api/v1/quality/benchmarks/bench-1"),
+    ("POST", "/api/v1/quality/runs"),
+    ("POST", "/api/v1/network/tls/reload"),
+    ("PUT", "/api/v1/admin/log-levels"),
+    ("DELETE", "/api/v1/admin/log-levels/hal9_server"),
+    ("PUT", "/api/v1/admin/config/overrides"),
+    ("DELETE", "/api/v1/admin/config/overrides/claude.model"),
+    ("PUT", "/api/v1/admin/warm-pools/wasm"),
+    ("POST", "/api/v1/admin/topology/patch"),
+    ("POST", "/api/v1/admin/topology/revert"),
+    ("POST", "/api/v1/admin/layers/L2/pause"),
+    ("POST", "/api/v1/admin/layers/L2/resume"),
+    ("POST", "/api/v1/admin/ingestion/github/pause"),
+    ("POST", "/api/v1/admin/ingestion/github/resume"),
+    ("POST", "/api/v1/admin/retention/run"),
+    ("PUT", "/api/v1/admin/mock/scenario"),
+    ("POST", "/api/v1/admin/mock/recording/save"),
+    ("PUT", "/api/v1/admin/orgs/acme/encryption"),
+    ("POST", "/api/v1/admin/orgs/acme/encryption/rotate"),
+    ("POST", "/api/v1/auth/register"),
+    ("POST", "/api/v1/auth/login"),
+    ("POST", "/api/v1/auth/refresh"),
+    ("PUT", "/api/v1/auth/profile"),
+    ("POST", "/api/v1/auth/api-keys"),
+    ("PUT", "/api/v1/auth/api-keys/key-1"),
+    ("DELETE", "/api/v1/auth/api-keys/key-1"),
+    ("POST", "/api/v1/keys"),
+    ("DELETE", "/api/v1/keys/key-1"),
+    ("POST", "/api/v1/keys/key-1/rotate"),
+    ("POST", "/api/v1/keys/key-1/revoke"),
+    ("POST", "/api/v1/codegen/project"),
+    ("POST", "/api/v1/codegen/complete"),
+    ("POST", "/api/v1/codegen/review"),
+    ("POST", "/api/v1/codegen/refactor"),
+    ("POST", "/mcp"),
+    ("POST", "/genius/api/games"),
+    ("POST", "/genius/api/games/game-1/start"),
+];
+
+fn config() -> ServerConfig {
+    common::mock_config(json!({
+        "server_id": "read-only-test",
+        "neurons": [
+            {"id": "planner", "layer": "L4", "forward_connections": ["coder"], "backward_connections": []},
+            {"id": "coder", "layer": "L2", "forward_connections": [], "backward_connections": ["planner"]},
+        ],
+        "claude": {
+            "mock_responses": {
+                "L4": [{"trigger": "default", "response": "FORWARD_TO: coder\nCONTENT: Build it", "delay_ms": 0}],
+                "L2": [{"trigger": "default", "response": "RESULT: Built", "delay_ms": 0}],
+            },
+        },
+        "memory": {"enabled": false},
+        "read_only": {"retry_after_secs": 120},
+    }))
+}
+
+async fn send(app: &Router, method: &str, uri: &str, body: Option<Value>) -> (StatusCode, Option<String>, Value) {
+    let reply = common::send(app, common::request(method, uri, &[], body)).await;
+    let retry_after = reply.header(header::RETRY_AFTER).map(str::to_string);
+    (reply.status, retry_after, reply.json())
+}
+
+async fn finished_chain(server: &HAL9Server) -> String {
+    let signal = NeuronSignal::forward("api-client", "planner", "API", "L4", "Plan it".into());
+    let chain_id = server.submit_signal(signal).await.unwrap();
+    tokio::time::timeout(Duration::from_secs(10), async {
+        while server.get_chain_result(&chain_id).await.unwrap().status == ChainStatus::Running {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("chain did not finish");
+    chain_id
+}
+
+#[tokio::test]
+async fn test_mutating_routes_refused_while_reads_work() {
+    let server = Arc::new(HAL9Server::new(config()));
+    server.start().await.unwrap();
+    let chain_id = finished_chain(&server).await;
+    let app = create_api_router(server.clone());
+
+    let (status, _, body) = send(
+        &app,
+        "POST",
+        "/api/v1/admin/readonly",
+        Some(json!({"enabled": true, "reason": "database upgrade"})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["data"]["status"]["active"], true);
+
+    for (method, path) in MUTATING_ROUTES {
+        assert!(is_mutating(&method.parse::<Method>().unwrap(), path), "{} {}", method, path);
+        let (status, retry_after, body) = send(&app, method, path, Some(json!({}))).await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE, "{} {}: {}", method, path, body);
+        assert_eq!(retry_after.as_deref(), Some("120"), "{} {}", method, path);
+        let error: ErrorResponse = serde_json::from_value(body).unwrap();
+        assert_eq!(error.error.error_code(), ErrorCode::Maintenance);
+        assert!(error.error.error_code().is_retryable());
+        assert_eq!(error.retry_after, Some(120));
+        assert_eq!(error.error.details.unwrap()["reason"], "database upgrade");
+    }
+
+    // Chain results, costs, neurons and status are still served
+    for path in [
+        format!("/api/v1/chains/{}", chain_id),
+        "/api/v1/costs".to_string(),
+        "/api/v1/neurons".to_string(),
+        "/api/v1/neurons/planner".to_string(),
+        "/api/v1/admin/readonly".to_string(),
+    ] {
+        let (status, _, body) = send(&app, "GET", &path, None).await;
+        assert_eq!(status, StatusCode::OK, "{}: {}", path, body);
+    }
+
+    // The mode shows in status, health and metrics
+    let (_, _, status) = send(&app, "GET", "/api/v1/status", None).await;
+    let read_only = &status["data"]["read_only"];
+    assert_eq!(read_only["active"], true);
+    assert_eq!(read_only["reason"], "database upgrade");
+    assert_eq!(read_only["refused_requests"], MUTATING_ROUTES.len());
+    let (_, _, health) = send(&app, "GET", "/health", None).await;
+    assert_eq!(health["read_only"], true);
+    let (_, _, detail) = send(&app, "GET", "/health/detail", None).await;
+    let component = detail["components"].as_array().unwrap().iter().find(|c| c["name"] == "read_only").unwrap();
+    assert_eq!(component["status"], "degraded");
+    let metrics = common::send(&app, common::request("GET", "/metrics", &[], None)).await.text();
+    assert!(metrics.contains("hal9_read_only{server_id=\"read-only-test\"} 1\n"), "{}", metrics);
+
+    // Turning the mode off lets writes through again
+    let (status, _, body) = send(&app, "POST", "/api/v1/admin/readonly", Some(json!({"enabled": false}))).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["data"]["status"]["active"], false);
+    let (status, _, body) = send(&app, "POST", "/api/v1/signal", Some(json!({"content": "Plan it", "layer": "L4"}))).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["success"], true);
+
+    server.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_read_only_from_config() {
+    let mut config = config();
+    config.read_only.enabled = true;
+    config.read_only.reason = Some("restore from backup".to_string());
+    let server = Arc::new(HAL9Server::new(config));
+    let app = create_api_router(server.clone());
+
+    let (status, _, body) = send(&app, "POST", "/api/v1/signal", Some(json!({"content": "Plan it", "layer": "L4"}))).await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(body["error"]["code"], "MAINTENANCE");
+    let state = server.read_only().state().unwrap();
+    assert_eq!(state.enabled_by.as_deref(), Some("config"));
+    assert_eq!(state.reason.as_deref(), Some("restore from backup"));
+
+    // Preflight requests and draining are still answered
+    let (status, _, _) = send(&app, "OPTIONS", "/api/v1/signal", None).await;
+    assert_ne!(status, StatusCode::SERVICE_UNAVAILABLE);
+    let (status, _, _) = send(&app, "POST", "/api/v1/admin/drain", None).await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+async fn memory() -> (Arc<NamespacedMemory>, Arc<dyn MemoryStore>) {
+    let store = SqliteMemoryStore::in_memory().await.unwrap();
+    store.initialize().await.unwrap();
+    let store: Arc<dyn MemoryStore> = Arc::new(store);
+    (Arc::new(NamespacedMemory::new(store.clone(), MemoryNamespacesConfig::default())), store)
+}
+
+fn write(memory: &Arc<NamespacedMemory>, content: &str) -> MemoryWrite {
+    let entry = MemoryBuilder::new("coder".to_string(), "L2".to_string())
+        .with_content(content.to_string())
+        .build();
+    MemoryWrite::new(memory.clone(), "coder", "L2", &MemoryNamespace::Private("coder".to_string()), entry)
+}
+
+#[tokio::test]
+async fn test_memory_writes_held_until_mode_ends() {
+    let (memory, store) = memory().await;
+    let gate = ReadOnlyGate::new(ReadOnlyConfig {
+        memory_writes: MemoryWritePolicy::Hold,
+        max_held_writes: 2,
+        ..Default::default()
+    });
+    gate.enable(Some("vacuum".to_string()), None, Some("ops"));
+
+    let mut held = Vec::new();
+    for content in ["first", "second", "third"] {
+        match gate.admit_memory_write(write(&memory, content)) {
+            MemoryWriteAdmission::Held(id) => held.push(id),
+            MemoryWriteAdmission::Skipped => assert_eq!(content, "third"),
+            MemoryWriteAdmission::Write(_) => panic!("{} written while read-only", content),
+        }
+    }
+    let status = gate.status();
+    assert_eq!((status.held_writes, status.skipped_writes), (2, 1));
+    assert!(store.get(held[0]).await.unwrap().is_none());
+
+    assert_eq!(gate.disable(Some("ops")).await, 2);
+    for id in &held {
+        assert!(store.get(*id).await.unwrap().is_some());
+    }
+    assert_eq!(gate.status().held_writes, 0);
+    assert!(matches!(gate.admit_memory_write(write(&memory, "after")), MemoryWriteAdmission::Write(_)));
+}
+
+#[tokio::test]
+async fn test_memory_writes_skipped_by_default() {
+    let (memory, _) = memory().await;
+    let gate = ReadOnlyGate::new(ReadOnlyConfig { enabled: true, ..Default::default() });
+
+    assert!(matches!(gate.admit_memory_write(write(&memory, "note")), MemoryWriteAdmission::Skipped));
+    assert_eq!(gate.status().skipped_writes, 1);
+    assert_eq!(gate.disable(None).await, 0);
+}
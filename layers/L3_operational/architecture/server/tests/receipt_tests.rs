@@ -0,0 +1,163 @@
+//! Tests for chain receipts: inclusion proofs and anchoring
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use parking_lot::Mutex;
+
+use hal9_core::receipt::{ChainReceipt, ReceiptLeaf, ReceiptSigner};
+use hal9_core::NeuronSignal;
+use hal9_server::chain_tracker::{ChainTracker, PARENT_ID_KEY};
+use hal9_server::error::ServerError;
+use hal9_server::receipts::{Anchor, ReceiptManager};
+
+/// Records anchored roots, optionally failing every call
+struct MockAnchor {
+    roots: Mutex<Vec<String>>,
+    fail: bool,
+}
+
+impl MockAnchor {
+    fn new(fail: bool) -> Arc<Self> {
+        Arc::new(Self {
+            roots: Mutex::new(Vec::new()),
+            fail,
+        })
+    }
+}
+
+#[async_trait]
+impl Anchor for MockAnchor {
+    fn name(&self) -> &str {
+        "mock"
+    }
+
+    async fn anchor(&self, receipt: &ChainReceipt) -> anyhow::Result<Option<String>> {
+        if self.fail {
+            anyhow::bail!("node unreachable");
+        }
+        let mut roots = self.roots.lock();
+        roots.push(receipt.merkle_root.clone());
+        Ok(Some(format!("0xtx{}", roots.len())))
+    }
+}
+
+/// A finished chain of a root and two children; returns the chain ID and
+/// the signals in processing order
+fn finished_chain(tracker: &ChainTracker) -> (String, Vec<NeuronSignal>) {
+    let mut root = NeuronSignal::forward("api", "planner", "API", "L4", "plan it".into());
+    let chain_id = tracker.start(&mut root);
+    tracker.record_usage(&root, 200, 80);
+    tracker.record_model(&root, "claude-3-sonnet");
+    tracker.record_step(&root, Ok("FORWARD_TO: coder, tester"), 2);
+
+    let mut signals = vec![root.clone()];
+    for (neuron, output) in [("coder", "fn main() {}"), ("tester", "all green")] {
+        let mut child = NeuronSignal::forward("planner", neuron, "L4", "L2", "work".into());
+        child.metadata = root.metadata.clone();
+        child.metadata.insert(PARENT_ID_KEY.to_string(), root.signal_id.to_string());
+        tracker.record_usage(&child, 100, 40);
+        tracker.record_step(&child, Ok(output), 0);
+        signals.push(child);
+    }
+    (chain_id, signals)
+}
+
+#[tokio::test]
+async fn test_proof_verifies_each_included_signal() {
+    let tracker = ChainTracker::new();
+    let (chain_id, signals) = finished_chain(&tracker);
+    let manager = ReceiptManager::new(ReceiptSigner::generate(), MockAnchor::new(false));
+    let record = tracker.get(&chain_id).unwrap();
+
+    for signal in &signals {
+        let signal_id = signal.signal_id.to_string();
+        let response = manager.receipt(&record, Some(&signal_id)).await.unwrap();
+        assert_eq!(response.receipt.leaf_count, 3);
+        let proof = response.proof.unwrap();
+        assert_eq!(proof.leaf.signal_id, signal_id);
+        response
+            .receipt
+            .verify_inclusion(&manager.public_key(), &proof.leaf, &proof.proof)
+            .unwrap();
+    }
+
+    let response = manager.receipt(&record, Some(&signals[1].signal_id.to_string())).await.unwrap();
+    let proof = response.proof.unwrap();
+    assert_eq!(proof.leaf.content_sha256, ReceiptLeaf::content_hash("fn main() {}"));
+    assert_eq!(proof.leaf.model, None);
+    assert_eq!(proof.leaf.prompt_tokens, 100);
+}
+
+#[tokio::test]
+async fn test_tampered_content_fails_verification() {
+    let tracker = ChainTracker::new();
+    let (chain_id, signals) = finished_chain(&tracker);
+    let manager = ReceiptManager::new(ReceiptSigner::generate(), MockAnchor::new(false));
+    let record = tracker.get(&chain_id).unwrap();
+    let response = manager.receipt(&record, Some(&signals[0].signal_id.to_string())).await.unwrap();
+    let proof = response.proof.unwrap();
+    assert_eq!(proof.leaf.model.as_deref(), Some("claude-3-sonnet"));
+
+    let mut leaf = proof.leaf.clone();
+    leaf.content_sha256 = ReceiptLeaf::content_hash("FORWARD_TO: attacker");
+    assert!(response.receipt.verify_inclusion(&manager.public_key(), &leaf, &proof.proof).is_err());
+
+    let mut leaf = proof.leaf.clone();
+    leaf.model = Some("claude-3-haiku".to_string());
+    assert!(response.receipt.verify_inclusion(&manager.public_key(), &leaf, &proof.proof).is_err());
+
+    // Another server's key does not vouch for this receipt
+    let stranger = ReceiptSigner::generate();
+    assert!(response
+        .receipt
+        .verify_inclusion(&stranger.public_key(), &proof.leaf, &proof.proof)
+        .is_err());
+}
+
+#[tokio::test]
+async fn test_receipt_is_anchored_once() {
+    let tracker = ChainTracker::new();
+    let (chain_id, _) = finished_chain(&tracker);
+    let anchor = MockAnchor::new(false);
+    let manager = ReceiptManager::new(ReceiptSigner::generate(), anchor.clone());
+    let record = tracker.get(&chain_id).unwrap();
+
+    let first = manager.receipt(&record, None).await.unwrap();
+    assert!(first.proof.is_none());
+    let anchored = first.receipt.anchor.clone().unwrap();
+    assert_eq!(anchored.anchor, "mock");
+    assert_eq!(anchored.reference, "0xtx1");
+    assert_eq!(*anchor.roots.lock(), vec![first.receipt.merkle_root.clone()]);
+
+    let second = manager.receipt(&record, None).await.unwrap();
+    assert_eq!(second.receipt, first.receipt);
+    assert_eq!(anchor.roots.lock().len(), 1);
+}
+
+#[tokio::test]
+async fn test_failed_anchor_still_issues_receipt() {
+    let tracker = ChainTracker::new();
+    let (chain_id, _) = finished_chain(&tracker);
+    let manager = ReceiptManager::new(ReceiptSigner::generate(), MockAnchor::new(true));
+    let record = tracker.get(&chain_id).unwrap();
+
+    let response = manager.receipt(&record, None).await.unwrap();
+    assert!(response.receipt.anchor.is_none());
+    response.receipt.verify(&manager.public_key()).unwrap();
+}
+
+#[tokio::test]
+async fn test_running_chain_and_unknown_signal_are_rejected() {
+    let tracker = ChainTracker::new();
+    let manager = ReceiptManager::new(ReceiptSigner::generate(), MockAnchor::new(false));
+
+    let mut root = NeuronSignal::forward("api", "planner", "API", "L4", "plan it".into());
+    let chain_id = tracker.start(&mut root);
+    let running = manager.receipt(&tracker.get(&chain_id).unwrap(), None).await;
+    assert!(matches!(running, Err(ServerError::InvalidInput(_))));
+
+    let (chain_id, _) = finished_chain(&tracker);
+    let unknown = manager.receipt(&tracker.get(&chain_id).unwrap(), Some("not-a-signal")).await;
+    assert!(matches!(unknown, Err(ServerError::NotFound(_))));
+}
@@ -0,0 +1,187 @@
+//! Recovery playbooks: step order, metadata trail, config and routing
+
+mod common;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde_json::{json, Value};
+
+use hal9_core::{config::RecoveryPlaybookConfig, NeuronInterface, NeuronSignal, Result};
+use hal9_server::{
+    claude::{ClaudeInterface, MockClaude},
+    concurrency::DeadLetterQueue,
+    metrics::Metrics,
+    neuron::{ManagedNeuron, NeuronRegistry},
+    recovery::{
+        DeadLetterStep, FallbackModelStep, RecoveryContext, RecoveryOutcome, RecoveryPlaybook, RerouteSiblingStep,
+        RetryStep, SummarizeAndDegradeStep, RECOVERY_ERROR_KEY, RECOVERY_OUTCOME_KEY, RECOVERY_STEPS_KEY,
+    },
+    router::{RoutingTable, SignalRouter},
+};
+
+use common::neuron_config;
+
+fn scripted(layer: &str, outcomes: Vec<std::result::Result<&str, &str>>) -> MockClaude {
+    let outcomes = outcomes.into_iter().map(|o| o.map(str::to_string).map_err(str::to_string)).collect();
+    MockClaude::scripted_outcomes(layer, outcomes)
+}
+
+fn failing_neuron(id: &str, failures: usize, metrics: &Arc<Metrics>) -> ManagedNeuron {
+    let claude = scripted("L2", vec![Err("provider overloaded"); failures]);
+    let mut neuron = ManagedNeuron::new(neuron_config(id, "L2", &[], Value::Null), Box::new(claude)).unwrap();
+    neuron.set_metrics(metrics.clone());
+    neuron
+}
+
+fn mock_claude_for(model: &str) -> Result<Box<dyn ClaudeInterface>> {
+    Ok(Box::new(scripted("L2", vec![Ok(model)])))
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_every_step_runs_in_order_until_dead_letter() {
+    let metrics = Arc::new(Metrics::new());
+    // One failure starts recovery, then one per retry attempt
+    let neuron = failing_neuron("impl", 3, &metrics);
+    let sibling = Arc::new(failing_neuron("impl-clone-1", 1, &metrics));
+    let playbook = RecoveryPlaybook::new(vec![
+        Box::new(RetryStep::new(2, Duration::from_millis(100), Duration::from_secs(1))),
+        Box::new(FallbackModelStep::new(Box::new(scripted("L2", vec![Err("fallback model unavailable")])))),
+        Box::new(RerouteSiblingStep),
+        Box::new(SummarizeAndDegradeStep::new(Box::new(scripted("L2", vec![Err("summarizer unavailable")])), 200)),
+        Box::new(DeadLetterStep),
+    ]);
+    assert_eq!(
+        playbook.step_names(),
+        vec!["retry", "fallback_model", "reroute_sibling", "summarize_and_degrade", "dead_letter"]
+    );
+
+    let signal = NeuronSignal::forward("design", "impl", "L3", "L2", "build the parser".to_string());
+    let error = neuron.process_signal(&signal).await.unwrap_err().to_string();
+    let dead_letters = DeadLetterQueue::default();
+    let ctx = RecoveryContext { signal: &signal, neuron: &neuron, error: &error, siblings: vec![sibling], dead_letters: &dead_letters };
+    let report = playbook.run(&ctx).await;
+
+    let trail = "retry:failed,fallback_model:failed,reroute_sibling:failed,summarize_and_degrade:failed,dead_letter:dead_lettered";
+    assert_eq!(report.outcome, RecoveryOutcome::DeadLettered);
+    assert!(report.response.is_none());
+    let mut metadata = HashMap::new();
+    report.apply_to(&mut metadata);
+    assert_eq!(metadata[RECOVERY_STEPS_KEY], trail);
+    assert_eq!(metadata[RECOVERY_OUTCOME_KEY], "dead_lettered");
+    assert!(metadata[RECOVERY_ERROR_KEY].contains("provider overloaded"));
+
+    // The parked signal carries the whole path for a postmortem
+    let letters = dead_letters.list();
+    assert_eq!(letters.len(), 1);
+    assert_eq!((letters[0].neuron_id.as_str(), letters[0].reason.as_str()), ("impl", "recovery_exhausted"));
+    assert_eq!(letters[0].signal.signal_id, signal.signal_id);
+    assert_eq!(letters[0].signal.metadata[RECOVERY_STEPS_KEY], trail);
+
+    let steps = metrics.snapshot().recovery_steps;
+    assert_eq!(steps.len(), 5);
+    assert!(steps.iter().all(|(_, count)| *count == 1), "{:?}", steps);
+    assert_eq!(steps["dead_letter:dead_lettered"], 1);
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_playbook_stops_at_first_step_that_answers() {
+    let metrics = Arc::new(Metrics::new());
+    // The retry fails once, then its second attempt succeeds
+    let neuron = failing_neuron("impl", 2, &metrics);
+    let playbook = RecoveryPlaybook::new(vec![
+        Box::new(RerouteSiblingStep),
+        Box::new(SummarizeAndDegradeStep::new(Box::new(scripted("L2", vec![Ok("partial answer")])), 7)),
+        Box::new(DeadLetterStep),
+    ]);
+
+    let signal = NeuronSignal::forward("design", "impl", "L3", "L2", "build the parser".to_string());
+    let error = neuron.process_signal(&signal).await.unwrap_err().to_string();
+    let dead_letters = DeadLetterQueue::default();
+    let ctx = RecoveryContext { signal: &signal, neuron: &neuron, error: &error, siblings: vec![], dead_letters: &dead_letters };
+    let report = playbook.run(&ctx).await;
+    assert_eq!(report.outcome, RecoveryOutcome::Degraded);
+    assert_eq!(report.response.as_deref(), Some("partial"));
+    let trail: Vec<_> = report.steps.iter().map(|r| format!("{}:{}", r.step, r.outcome)).collect();
+    assert_eq!(trail, vec!["reroute_sibling:skipped", "summarize_and_degrade:degraded"]);
+    assert!(dead_letters.is_empty());
+
+    let retry = RecoveryPlaybook::new(vec![
+        Box::new(RetryStep::new(3, Duration::from_millis(100), Duration::from_secs(1))),
+        Box::new(DeadLetterStep),
+    ]);
+    let report = retry.run(&ctx).await;
+    assert_eq!(report.outcome, RecoveryOutcome::Recovered);
+    assert_eq!(report.steps.len(), 1);
+    assert_eq!(metrics.snapshot().recovery_steps["retry:recovered"], 1);
+}
+
+#[test]
+fn test_playbook_config_and_neuron_override() {
+    let layers: HashMap<String, RecoveryPlaybookConfig> = serde_json::from_value(json!({
+        "L2": {"steps": [
+            {"type": "retry", "attempts": 2},
+            {"type": "fallback_model", "model": "claude-3-haiku-20240307"},
+            {"type": "reroute_sibling"},
+            {"type": "summarize_and_degrade"},
+            {"type": "dead_letter"}
+        ]}
+    }))
+    .unwrap();
+
+    let from_layer = RecoveryPlaybook::for_neuron(&layers, &neuron_config("impl", "L2", &[], Value::Null), &mock_claude_for)
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        from_layer.step_names(),
+        vec!["retry", "fallback_model", "reroute_sibling", "summarize_and_degrade", "dead_letter"]
+    );
+
+    let overridden = neuron_config("impl", "L2", &[], json!({"recovery": {"steps": [{"type": "dead_letter"}]}}));
+    let playbook = RecoveryPlaybook::for_neuron(&layers, &overridden, &mock_claude_for).unwrap().unwrap();
+    assert_eq!(playbook.step_names(), vec!["dead_letter"]);
+
+    // No playbook for the layer, or one with no steps, leaves recovery off
+    let design = neuron_config("design", "L3", &[], Value::Null);
+    assert!(RecoveryPlaybook::for_neuron(&layers, &design, &mock_claude_for).unwrap().is_none());
+    let disabled = neuron_config("impl", "L2", &[], json!({"recovery": {"steps": []}}));
+    assert!(RecoveryPlaybook::for_neuron(&layers, &disabled, &mock_claude_for).unwrap().is_none());
+
+    let unknown = neuron_config("impl", "L2", &[], json!({"recovery": {"steps": [{"type": "pray"}]}}));
+    let err = RecoveryPlaybook::for_neuron(&layers, &unknown, &mock_claude_for).err().unwrap();
+    assert!(err.to_string().contains("Invalid recovery settings for neuron impl"), "{}", err);
+}
+
+#[tokio::test]
+async fn test_router_forwards_recovered_response() {
+    let metrics = Arc::new(Metrics::new());
+    let mut registry = NeuronRegistry::new();
+    registry.set_metrics(metrics.clone());
+    let registry = Arc::new(registry);
+
+    let design = neuron_config("design", "L3", &["impl"], Value::Null);
+    let implementation = neuron_config("impl", "L2", &[], Value::Null);
+    let mut neuron = ManagedNeuron::new(design.clone(), Box::new(scripted("L3", vec![Err("provider overloaded")]))).unwrap();
+    let fallback = scripted("L3", vec![Ok("FORWARD_TO: impl\nCONTENT:\nbuild the parser")]);
+    neuron.set_recovery(RecoveryPlaybook::new(vec![
+        Box::new(FallbackModelStep::new(Box::new(fallback))),
+        Box::new(DeadLetterStep),
+    ]));
+    registry.register(neuron).await.unwrap();
+    registry
+        .register(ManagedNeuron::new(implementation.clone(), Box::new(scripted("L2", vec![Ok("done")]))).unwrap())
+        .await
+        .unwrap();
+
+    let routing_table = Arc::new(RoutingTable::new());
+    routing_table.build_from_configs(&[design, implementation]);
+    let mut router = SignalRouter::new(registry.clone(), routing_table);
+    router.start().await.unwrap();
+    router.send_signal(NeuronSignal::forward("client", "design", "L4", "L3", "design a parser".to_string())).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(300)).await;
+
+    assert_eq!(registry.get("impl").unwrap().health().await.unwrap().signals_processed, 1);
+    assert!(registry.dead_letters().is_empty());
+    assert_eq!(metrics.snapshot().recovery_steps["fallback_model:recovered"], 1);
+}
@@ -0,0 +1,278 @@
+//! Differential privacy noise on exported usage reports: calibration of the
+//! Laplace noise, privacy budgets and suppression of small counts
+
+mod common;
+
+use std::collections::BTreeMap;
+
+use axum::http::StatusCode;
+use chrono::{TimeZone, Utc};
+use rand::{rngs::StdRng, SeedableRng};
+use serde_json::Value;
+
+use hal9_core::auth::Permissions;
+use hal9_core::config::{BudgetPeriod, ReportPrivacyConfig};
+use hal9_core::sqlite::{SqlitePools, SqliteTuning};
+use hal9_server::{
+    api::create_api_router,
+    auth_middleware::AuthUser,
+    cost_tags::{TagCombination, TagGroup, TagSpend, TagSpendReport},
+    dev::{self, DevOptions},
+    error::ServerError,
+    org_usage::{OrgUsage, UserSpend},
+    report_privacy::{budget_key, laplace, require_caller, ReportPrivacy},
+};
+
+use common::{request, send};
+
+fn spend(chains: u64) -> TagSpend {
+    TagSpend {
+        chains,
+        signals: chains * 3,
+        prompt_tokens: chains * 1_000,
+        completion_tokens: chains * 400,
+        cost_usd: chains as f64 * 0.02,
+    }
+}
+
+fn group(value: &str, chains: u64) -> TagGroup {
+    let tags = BTreeMap::from([("team".to_string(), value.to_string())]);
+    TagGroup {
+        value: Some(value.to_string()),
+        spend: spend(chains),
+        combinations: vec![TagCombination { tags, spend: spend(chains) }],
+    }
+}
+
+fn report(groups: Vec<TagGroup>) -> TagSpendReport {
+    let total = groups.iter().map(|group| group.spend.chains).sum();
+    TagSpendReport {
+        key: "team".to_string(),
+        period: BudgetPeriod::Monthly,
+        period_start: Utc.with_ymd_and_hms(2026, 10, 1, 0, 0, 0).unwrap(),
+        period_end: Utc.with_ymd_and_hms(2026, 11, 1, 0, 0, 0).unwrap(),
+        total: spend(total),
+        groups,
+    }
+}
+
+fn privacy(max_epsilon: f64) -> ReportPrivacy {
+    ReportPrivacy::new(ReportPrivacyConfig { max_epsilon, ..Default::default() })
+}
+
+fn mean_and_variance(samples: &[f64]) -> (f64, f64) {
+    let n = samples.len() as f64;
+    let mean = samples.iter().sum::<f64>() / n;
+    let variance = samples.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (n - 1.0);
+    (mean, variance)
+}
+
+#[tokio::test]
+async fn test_noise_is_calibrated() {
+    let mut rng = StdRng::seed_from_u64(7);
+
+    // Laplace(b) has mean 0, variance 2b² and P(|x| > b) = 1/e
+    let scale = 2.0;
+    let samples: Vec<f64> = (0..200_000).map(|_| laplace(&mut rng, scale)).collect();
+    let (mean, variance) = mean_and_variance(&samples);
+    assert!(mean.abs() < 0.05, "mean {}", mean);
+    assert!((variance / (2.0 * scale * scale) - 1.0).abs() < 0.03, "variance {}", variance);
+    let beyond = samples.iter().filter(|x| x.abs() > scale).count() as f64 / samples.len() as f64;
+    assert!((beyond - (-1.0f64).exp()).abs() < 0.005, "P(|x| > b) = {}", beyond);
+
+    // Released counts are unbiased with the scale reported alongside them
+    let privacy = privacy(f64::MAX);
+    let report = report(vec![group("search", 6_000), group("billing", 4_000)]);
+    let epsilon = 2.0;
+    let mut released = Vec::new();
+    let mut reported_scale = 0.0;
+    for _ in 0..4_000 {
+        let private = privacy.release_with("key:dashboard", epsilon, &report, &mut rng).await.unwrap();
+        let params = &private.privacy;
+        // total, two groups and their combinations, five statistics each
+        assert_eq!(params.statistics, 25);
+        assert!((params.epsilon_per_statistic - epsilon / 25.0).abs() < 1e-12);
+        reported_scale = params.noise["count"].scale;
+        released.push(private.report["total"]["chains"].as_f64().unwrap());
+    }
+    assert!((reported_scale - 1.0 / (epsilon / 25.0)).abs() < 1e-9);
+    let (mean, variance) = mean_and_variance(&released);
+    let std_dev = variance.sqrt();
+    let expected_std_dev = reported_scale * 2f64.sqrt();
+    assert!((mean - 10_000.0).abs() < 4.0 * expected_std_dev / (released.len() as f64).sqrt(), "mean {}", mean);
+    assert!((std_dev / expected_std_dev - 1.0).abs() < 0.05, "std dev {} != {}", std_dev, expected_std_dev);
+
+    // Sums are scaled by what one chain may add
+    let params = privacy.release_with("key:dashboard", epsilon, &report, &mut rng).await.unwrap().privacy;
+    let config = ReportPrivacyConfig::default();
+    assert_eq!(params.noise["tokens"].sensitivity, config.max_tokens_per_chain as f64);
+    assert_eq!(params.noise["cost_usd"].sensitivity, config.max_cost_per_chain);
+    assert!((params.noise["cost_usd"].scale - config.max_cost_per_chain * 25.0 / epsilon).abs() < 1e-9);
+}
+
+#[tokio::test]
+async fn test_budget_exhaustion_is_rejected() {
+    let privacy = privacy(1.0);
+    let report = report(vec![group("search", 500)]);
+    let mut rng = StdRng::seed_from_u64(11);
+
+    let first = privacy.release_with("key:a", 0.4, &report, &mut rng).await.unwrap().privacy;
+    assert!((first.epsilon_spent - 0.4).abs() < 1e-12);
+    assert!((first.epsilon_remaining - 0.6).abs() < 1e-12);
+    let second = privacy.release_with("key:a", 0.6, &report, &mut rng).await.unwrap().privacy;
+    assert!(second.epsilon_remaining.abs() < 1e-12);
+
+    // Past the cap the query is refused and nothing more is charged
+    let err = privacy.release_with("key:a", 0.1, &report, &mut rng).await.unwrap_err();
+    assert!(matches!(err, ServerError::LimitExceeded(ref message) if message.contains("budget")), "{}", err);
+    assert!((privacy.spent("key:a") - 1.0).abs() < 1e-12);
+
+    // Budgets are per caller
+    privacy.release_with("key:b", 1.0, &report, &mut rng).await.unwrap();
+    assert!(privacy.release_with("key:b", 0.01, &report, &mut rng).await.is_err());
+
+    // A query over the whole cap is refused outright; bad epsilons charge nothing
+    assert!(matches!(privacy.release_with("key:c", 1.5, &report, &mut rng).await, Err(ServerError::LimitExceeded(_))));
+    for epsilon in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+        let err = privacy.release_with("key:c", epsilon, &report, &mut rng).await.unwrap_err();
+        assert!(matches!(err, ServerError::InvalidInput(_)), "{}", err);
+    }
+    assert_eq!(privacy.spent("key:c"), 0.0);
+
+    // Queries are charged to the API key, else the user
+    let mut user = AuthUser {
+        user_id: "u1".to_string(),
+        username: "ada".to_string(),
+        role: "user".to_string(),
+        permissions: Permissions::new(),
+        org_id: Some("acme".to_string()),
+        api_key_id: Some("k1".to_string()),
+    };
+    assert_eq!(budget_key(&user), "org:acme/key:k1");
+    user.api_key_id = None;
+    assert_eq!(budget_key(&user), "org:acme/user:u1");
+    user.org_id = None;
+    assert_eq!(budget_key(&user), "user:u1");
+
+    // Anonymous callers have no budget to charge
+    assert_eq!(require_caller(Some(&user)).unwrap(), "user:u1");
+    assert!(matches!(require_caller(None), Err(ServerError::Forbidden(_))));
+}
+
+#[tokio::test]
+async fn test_spent_epsilon_survives_a_restart() {
+    let pools = SqlitePools::connect("sqlite::memory:", SqliteTuning::default()).await.unwrap();
+    let report = report(vec![group("search", 500)]);
+    let mut rng = StdRng::seed_from_u64(5);
+
+    let before = privacy(1.0);
+    before.attach_store(pools.clone()).await.unwrap();
+    before.release_with("org:acme/key:a", 0.4, &report, &mut rng).await.unwrap();
+    before.release_with("org:acme/key:a", 0.2, &report, &mut rng).await.unwrap();
+    before.release_with("org:globex/key:b", 0.3, &report, &mut rng).await.unwrap();
+
+    // A restarted server picks up what each caller spent
+    let after = privacy(1.0);
+    after.attach_store(pools.clone()).await.unwrap();
+    assert!((after.spent("org:acme/key:a") - 0.6).abs() < 1e-12);
+    assert!((after.spent("org:globex/key:b") - 0.3).abs() < 1e-12);
+    let err = after.release_with("org:acme/key:a", 0.5, &report, &mut rng).await.unwrap_err();
+    assert!(matches!(err, ServerError::LimitExceeded(_)), "{}", err);
+    let params = after.release_with("org:acme/key:a", 0.4, &report, &mut rng).await.unwrap().privacy;
+    assert!(params.epsilon_remaining.abs() < 1e-12);
+
+    // A store that fails to record the charge refuses the report
+    pools.writer().close().await;
+    let err = after.release_with("org:globex/key:b", 0.1, &report, &mut rng).await.unwrap_err();
+    assert!(matches!(err, ServerError::Internal(_)), "{}", err);
+    assert!((after.spent("org:globex/key:b") - 0.3).abs() < 1e-12);
+}
+
+#[tokio::test]
+async fn test_every_report_is_noised_for_an_authenticated_caller() {
+    let stack = dev::boot(&DevOptions::default()).await.unwrap();
+    let app = create_api_router(stack.server.clone());
+    let admin = stack.users.iter().find(|user| user.username == "admin").unwrap().api_key.clone();
+    let headers = [("x-api-key", admin.as_str())];
+
+    // Anonymous callers get no report, and no shared budget
+    for path in ["/api/v1/costs/by-tag?key=project", "/api/v1/orgs/acme/usage"] {
+        let reply = send(&app, request("GET", path, &[], None)).await;
+        assert_eq!(reply.status, StatusCode::UNAUTHORIZED, "{}", path);
+    }
+
+    // Without dp_epsilon, the default is charged
+    let default_epsilon = ReportPrivacyConfig::default().default_epsilon;
+    for query in 1..=2 {
+        let reply = send(&app, request("GET", "/api/v1/orgs/acme/usage", &headers, None)).await;
+        assert_eq!(reply.status, StatusCode::OK, "{}", reply.text());
+        let privacy = &reply.json()["privacy"];
+        assert_eq!(privacy["epsilon"].as_f64().unwrap(), default_epsilon);
+        assert!((privacy["epsilon_spent"].as_f64().unwrap() - default_epsilon * query as f64).abs() < 1e-9);
+    }
+
+    // Exact figures are not available in any format
+    for path in ["/api/v1/costs/by-tag?key=project&format=csv", "/api/v1/orgs/acme/usage?format=csv"] {
+        let reply = send(&app, request("GET", path, &headers, None)).await;
+        assert_eq!(reply.status, StatusCode::BAD_REQUEST, "{}", path);
+    }
+
+    stack.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_small_counts_are_suppressed() {
+    // Noise small enough to leave the counts on either side of the threshold
+    let privacy = privacy(f64::MAX);
+    let threshold = ReportPrivacyConfig::default().suppression_threshold;
+    let mut rng = StdRng::seed_from_u64(3);
+    let raw = report(vec![group("search", 5_000), group("legal", 2)]);
+    let before = raw.clone();
+
+    let private = privacy.release_with("key:a", 1e7, &raw, &mut rng).await.unwrap();
+    assert_eq!(private.privacy.suppression_threshold, threshold);
+    // The small group's chains and signals, and those of its combination
+    assert_eq!(private.privacy.suppressed, 4);
+    let groups = private.report["groups"].as_array().unwrap();
+    assert_eq!(groups.len(), 1);
+    assert_eq!(groups[0]["value"], "search");
+    assert_eq!(groups[0]["combinations"].as_array().unwrap().len(), 1);
+    assert_eq!(groups[0]["combinations"][0]["tags"]["team"], "search");
+    assert_eq!(private.report["total"]["chains"].as_u64().unwrap(), 5_002);
+    assert_eq!(raw, before, "the report itself is untouched");
+
+    // A small headline count is withheld in place
+    let private = privacy.release_with("key:a", 1e7, &report(vec![group("legal", 2)]), &mut rng).await.unwrap();
+    assert_eq!(private.report["total"]["chains"], Value::Null);
+    assert!(private.report["total"]["prompt_tokens"].is_u64());
+    assert!(private.report["groups"].as_array().unwrap().is_empty());
+
+    // Per-user rows and values derived from raw counts are left out
+    let usage = OrgUsage {
+        org_id: "acme".to_string(),
+        period: BudgetPeriod::Monthly,
+        period_start: Utc.with_ymd_and_hms(2026, 10, 1, 0, 0, 0).unwrap(),
+        period_end: Utc.with_ymd_and_hms(2026, 11, 1, 0, 0, 0).unwrap(),
+        chains_started: 120,
+        chains_completed: 110,
+        chains_failed: 4,
+        prompt_tokens: 90_000,
+        completion_tokens: 30_000,
+        cost_usd: 12.5,
+        by_layer: Vec::new(),
+        by_model: Vec::new(),
+        top_users: vec![UserSpend { user_id: "u1".to_string(), chains: 60, tokens: 60_000, cost_usd: 6.0 }],
+        webhook_deliveries: 40,
+        webhook_success_rate: Some(0.95),
+        dead_letters: 1,
+        avg_chain_latency_ms: Some(820.0),
+        comparison: None,
+    };
+    let private = privacy.release_with("key:a", 1e7, &usage, &mut rng).await.unwrap();
+    assert_eq!(private.privacy.withheld, vec!["avg_chain_latency_ms", "top_users", "webhook_success_rate"]);
+    assert!(private.report.get("top_users").is_none());
+    assert_eq!(private.report["chains_started"].as_u64().unwrap(), 120);
+    assert_eq!(private.report["chains_failed"], Value::Null);
+    assert_eq!(private.report["dead_letters"], Value::Null);
+    assert_eq!(private.privacy.statistics, 8);
+}
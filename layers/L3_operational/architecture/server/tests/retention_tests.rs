@@ -0,0 +1,222 @@
+//! Retention: what each record class keeps, dead letters holding their
+//! chains and signals, and archived chains that still resolve
+
+mod common;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::http::StatusCode;
+use chrono::Utc;
+use serde_json::json;
+
+use hal9_core::config::RetentionConfig;
+use hal9_core::sqlite::SqliteTuning;
+use hal9_core::NeuronSignal;
+use hal9_server::api::create_api_router;
+use hal9_server::chain_tracker::{ChainStatus, ChainTracker};
+use hal9_server::concurrency::DeadLetterQueue;
+use hal9_server::database_runtime::{DatabaseType, LoggedSignal, RuntimeDatabase};
+use hal9_server::retention::{Archive, RetentionJanitor, CHAINS, SIGNALS};
+use hal9_server::server::HAL9Server;
+
+use common::call;
+
+fn days(days: i64) -> chrono::Duration {
+    chrono::Duration::days(days)
+}
+
+fn retention(dir: &tempfile::TempDir) -> RetentionConfig {
+    RetentionConfig {
+        archive_dir: dir.path().display().to_string(),
+        ..Default::default()
+    }
+}
+
+/// A chain of one signal, finished
+fn finished_chain(tracker: &ChainTracker, content: &str) -> (String, NeuronSignal) {
+    let mut signal = NeuronSignal::forward("api-client", "planner", "API", "L4", content.into());
+    let chain_id = tracker.start(&mut signal);
+    tracker.record_step(&signal, Ok("RESULT: done"), 0);
+    assert_eq!(tracker.get(&chain_id).unwrap().status, ChainStatus::Completed);
+    (chain_id, signal)
+}
+
+#[tokio::test]
+async fn test_each_class_kept_for_its_own_retention() {
+    let dir = tempfile::tempdir().unwrap();
+    let tracker = Arc::new(ChainTracker::new());
+    let dead_letters = Arc::new(DeadLetterQueue::new(16));
+    let janitor = RetentionJanitor::new(retention(&dir), tracker.clone(), dead_letters.clone());
+    let (chain_id, signal) = finished_chain(&tracker, "Plan it");
+    dead_letters.push("coder", "queue full", NeuronSignal::forward("planner", "coder", "L4", "L2", "x".into()));
+    let now = Utc::now();
+
+    // Within payload retention nothing moves
+    let report = janitor.run(now + days(6)).await.unwrap();
+    assert_eq!((report.chains_archived, report.dead_letters_dropped), (0, 0));
+    assert!(tracker.get(&chain_id).is_some());
+    assert!(Archive::new(dir.path()).files(CHAINS).unwrap().is_empty());
+
+    // Past it the chain is archived and its summary kept
+    let report = janitor.run(now + days(8)).await.unwrap();
+    assert_eq!(report.chains_archived, 1);
+    assert_eq!(report.archive_files.len(), 1);
+    assert!(tracker.get(&chain_id).is_none());
+    let result = janitor.archived_result(&chain_id).await.unwrap().unwrap();
+    assert!(result.archived);
+    assert_eq!(result.final_output.as_deref(), Some("RESULT: done"));
+    assert_eq!(janitor.status().archived_summaries, 1);
+
+    // Dead letters go after 14 days, summaries after 90; the full record
+    // stays in the archive
+    let report = janitor.run(now + days(15)).await.unwrap();
+    assert_eq!((report.dead_letters_dropped, report.summaries_dropped), (1, 0));
+    assert!(dead_letters.is_empty());
+    let report = janitor.run(now + days(91)).await.unwrap();
+    assert_eq!(report.summaries_dropped, 1);
+    assert_eq!(janitor.status().archived_summaries, 0);
+    let record = janitor.archived_record(&chain_id).await.unwrap().unwrap();
+    assert_eq!(record.input, signal.payload.activation.content);
+    assert!(janitor.archived_result(&chain_id).await.unwrap().unwrap().archived);
+    assert_eq!(janitor.status().last_run.unwrap().ran_at, now + days(91));
+}
+
+#[tokio::test]
+async fn test_dead_letter_holds_its_chain() {
+    let dir = tempfile::tempdir().unwrap();
+    let tracker = Arc::new(ChainTracker::new());
+    let dead_letters = Arc::new(DeadLetterQueue::new(16));
+    let janitor = RetentionJanitor::new(retention(&dir), tracker.clone(), dead_letters.clone());
+    let (held, signal) = finished_chain(&tracker, "Plan it");
+    let (free, _) = finished_chain(&tracker, "Plan something else");
+    dead_letters.push("planner", "circuit open", signal);
+    let now = Utc::now();
+
+    let report = janitor.run(now + days(8)).await.unwrap();
+    assert_eq!((report.chains_archived, report.chains_held_by_dead_letters), (1, 1));
+    assert!(tracker.get(&held).is_some());
+    assert!(tracker.get(&free).is_none());
+
+    // Once the letter expires, its chain follows
+    let report = janitor.run(now + days(15)).await.unwrap();
+    assert_eq!((report.dead_letters_dropped, report.chains_archived), (1, 1));
+    assert!(tracker.get(&held).is_none());
+    assert!(janitor.archived_result(&held).await.unwrap().is_some());
+    assert_eq!(Archive::new(dir.path()).files(CHAINS).unwrap().len(), 2);
+}
+
+async fn signal_log(path: &std::path::Path) -> Arc<RuntimeDatabase> {
+    let url = format!("sqlite:{}?mode=rwc", path.display());
+    let database = Arc::new(RuntimeDatabase::connect(&url, DatabaseType::Sqlite, SqliteTuning::default()).await.unwrap());
+    for table in [
+        "CREATE TABLE signals (id TEXT PRIMARY KEY, from_neuron TEXT NOT NULL, to_neuron TEXT NOT NULL, \
+         layer_from TEXT NOT NULL, layer_to TEXT NOT NULL, content TEXT NOT NULL, timestamp INTEGER NOT NULL)",
+        "CREATE TABLE metrics_aggregate (id TEXT PRIMARY KEY, metric_name TEXT NOT NULL, neuron_id TEXT, \
+         value REAL NOT NULL, labels TEXT DEFAULT '{}', timestamp INTEGER NOT NULL)",
+    ] {
+        database.write(|pool| async move { sqlx::query(table).execute(&pool).await }).await.unwrap();
+    }
+    database
+}
+
+#[tokio::test]
+async fn test_signal_log_archived_then_deleted() {
+    let dir = tempfile::tempdir().unwrap();
+    let database = signal_log(&dir.path().join("signals.db")).await;
+    let mut signals = Vec::new();
+    for content in ["first", "second", "third"] {
+        let signal = NeuronSignal::forward("planner", "coder", "L4", "L2", content.into());
+        database.insert_signal(&signal).await.unwrap();
+        signals.push(signal);
+    }
+    database.record_cost("coder", "mock", 0.25).await.unwrap();
+
+    let tracker = Arc::new(ChainTracker::new());
+    let dead_letters = Arc::new(DeadLetterQueue::new(16));
+    let config = RetentionConfig {
+        archive_dir: dir.path().join("archive").display().to_string(),
+        ..Default::default()
+    };
+    let janitor = RetentionJanitor::new(config, tracker, dead_letters.clone());
+    janitor.set_signal_log(database.clone());
+    dead_letters.push("coder", "queue full", signals[1].clone());
+    let now = Utc::now();
+
+    let report = janitor.run(now + days(8)).await.unwrap();
+    assert_eq!((report.signals_archived, report.cost_records_deleted), (2, 0));
+    let archive = Archive::new(dir.path().join("archive"));
+    let files = archive.files(SIGNALS).unwrap();
+    assert_eq!(files.len(), 1);
+    let archived: Vec<LoggedSignal> = Archive::read(&files[0]).unwrap();
+    // Signals logged in the same second archive in id order
+    let mut contents: Vec<_> = archived.iter().map(|s| s.content.as_str()).collect();
+    contents.sort();
+    assert_eq!(contents, ["first", "third"]);
+    assert!(archived.iter().all(|s| s.timestamp == signals[0].timestamp.timestamp()));
+    assert!(database.get_signal(signals[0].signal_id).await.unwrap().is_none());
+    assert!(database.get_signal(signals[1].signal_id).await.unwrap().is_some());
+
+    // The held signal goes with its letter; cost records after 400 days
+    let report = janitor.run(now + days(401)).await.unwrap();
+    assert_eq!((report.signals_archived, report.cost_records_deleted), (1, 1));
+    assert_eq!(archive.files(SIGNALS).unwrap().len(), 2);
+
+    let reports = janitor.compact().await.unwrap();
+    assert_eq!(reports.len(), 1);
+    assert_eq!(reports[0].database, "signal_log");
+    // A log this small has no free pages to give back, and ANALYZE adds
+    // its statistics table, so only check that both sizes were read
+    assert!(reports[0].bytes_before > 0 && reports[0].bytes_after > 0, "{:?}", reports[0]);
+}
+
+#[tokio::test]
+async fn test_archived_chain_resolves_through_api() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = common::mock_config(json!({
+        "server_id": "retention-test",
+        "neurons": [
+            {"id": "planner", "layer": "L4", "forward_connections": [], "backward_connections": []},
+        ],
+        "claude": {
+            "mock_responses": {"L4": [{"trigger": "default", "response": "RESULT: Planned", "delay_ms": 0}]},
+        },
+        "memory": {"enabled": false},
+        "retention": {"payload_days": 0, "archive_dir": dir.path()},
+    }));
+    let server = Arc::new(HAL9Server::new(config));
+    server.start().await.unwrap();
+    let signal = NeuronSignal::forward("api-client", "planner", "API", "L4", "Plan it".into());
+    let chain_id = server.submit_signal(signal).await.unwrap();
+    tokio::time::timeout(Duration::from_secs(10), async {
+        while server.get_chain_result(&chain_id).await.unwrap().status == ChainStatus::Running {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("chain did not finish");
+    let app = create_api_router(server.clone());
+
+    let (status, body) = call(&app, "POST", "/api/v1/admin/retention/run", None).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["data"]["chains_archived"], 1);
+
+    let (status, body) = call(&app, "GET", &format!("/api/v1/chains/{}", chain_id), None).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["data"]["archived"], true);
+    assert_eq!(body["data"]["status"], "completed");
+    let graph = server.chain_graph(&chain_id).await.unwrap();
+    assert_eq!(graph.nodes.len(), 1);
+
+    let (_, body) = call(&app, "GET", "/api/v1/admin/retention", None).await;
+    assert_eq!(body["data"]["payload_days"], 0);
+    assert_eq!(body["data"]["archived_summaries"], 1);
+    assert_eq!(body["data"]["last_run"]["chains_archived"], 1);
+
+    // No SQLite databases are open with memory and auth off
+    let (status, body) = call(&app, "POST", "/api/v1/admin/compact", None).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["data"], json!([]));
+
+    server.shutdown().await.unwrap();
+}
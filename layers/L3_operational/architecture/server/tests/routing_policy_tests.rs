@@ -0,0 +1,191 @@
+//! Routing policies: each strategy's choice, deterministic tie-breaking,
+//! the fallback when no rule matches, and forwards left to the router
+
+use std::collections::HashMap;
+
+use serde_json::{json, Value};
+
+use hal9_core::{NeuronConfig, NeuronSignal};
+use hal9_server::neuron::ForwardInstruction;
+use hal9_server::router::policy::{
+    RoutingPolicy, RoutingStrategy, ANY_TARGET, FALLBACK_RULE, ROUTING_GROUP_KEY, ROUTING_ROUTE_KEY,
+    ROUTING_RULE_KEY, ROUTING_STRATEGY_KEY,
+};
+use hal9_server::{ManagedNeuron, MockClaude};
+
+fn planner(routing: Value) -> NeuronConfig {
+    NeuronConfig {
+        id: "planner".to_string(),
+        layer: "L4".to_string(),
+        claude_command: "claude".to_string(),
+        system_prompt: None,
+        forward_connections: vec!["devops".to_string(), "general".to_string(), "security".to_string()],
+        backward_connections: vec![],
+        settings: HashMap::from([("routing".to_string(), routing)]),
+    }
+}
+
+fn policy(routing: Value) -> RoutingPolicy {
+    RoutingPolicy::for_neuron(&planner(routing)).unwrap().expect("policy")
+}
+
+fn content_match() -> RoutingPolicy {
+    policy(json!({
+        "strategy": "content_match",
+        "group": "implementers",
+        "targets": ["devops", "general", "security"],
+        "fallback": "general",
+        "rules": [
+            {"name": "infrastructure", "target": "devops", "keywords": ["kubernetes", "load balancer"]},
+            {"name": "deploys", "target": "devops", "pattern": "(?i)\\bdeploy(ment)?s?\\b"},
+            {"name": "secrets", "target": "security", "keywords": ["credentials", "kubernetes"]},
+            {
+                "name": "audit",
+                "target": "security",
+                "examples": ["audit the access logs for intrusions"],
+                "min_similarity": 0.5,
+            },
+        ],
+    }))
+}
+
+fn idle(_: &str) -> usize {
+    0
+}
+
+#[tokio::test]
+async fn test_content_match_picks_the_best_rule() {
+    let policy = content_match();
+
+    let cases = [
+        ("Put a load balancer in front of the API", "devops", "infrastructure"),
+        ("Plan the deployments for Friday", "devops", "deploys"),
+        ("Rotate the CREDENTIALS of the billing service", "security", "secrets"),
+        // Similar to the example without sharing a keyword
+        ("Audit the access logs for intrusions last week", "security", "audit"),
+        // Keywords match whole words only
+        ("Redeploying is a kubernetesque idea", "general", FALLBACK_RULE),
+        ("Write the release notes", "general", FALLBACK_RULE),
+    ];
+    for (content, target, rule) in cases {
+        let route = policy.choose("implementers", content, idle).await;
+        assert_eq!((route.target.as_str(), route.rule.as_deref()), (target, Some(rule)), "{}", content);
+        assert_eq!(route.strategy, RoutingStrategy::ContentMatch);
+    }
+
+    // Both infrastructure and secrets match; the rule declared first wins,
+    // every time
+    for _ in 0..5 {
+        let route = policy.choose("implementers", "Store kubernetes credentials in the vault", idle).await;
+        assert_eq!(route.rule.as_deref(), Some("infrastructure"));
+    }
+}
+
+#[tokio::test]
+async fn test_route_is_recorded_in_metadata() {
+    let route = content_match().choose("implementers", "Scale the kubernetes cluster", idle).await;
+    let mut metadata = HashMap::from([(ROUTING_RULE_KEY.to_string(), "stale".to_string())]);
+    route.apply_to(&mut metadata);
+    assert_eq!(metadata[ROUTING_GROUP_KEY], "implementers");
+    assert_eq!(metadata[ROUTING_ROUTE_KEY], "devops");
+    assert_eq!(metadata[ROUTING_STRATEGY_KEY], "content_match");
+    assert_eq!(metadata[ROUTING_RULE_KEY], "infrastructure");
+
+    // Other strategies match no rule
+    let route = policy(json!({"strategy": "static"})).choose(ANY_TARGET, "Anything", idle).await;
+    route.apply_to(&mut metadata);
+    assert_eq!(metadata[ROUTING_GROUP_KEY], ANY_TARGET);
+    assert_eq!(metadata[ROUTING_STRATEGY_KEY], "static");
+    assert!(!metadata.contains_key(ROUTING_RULE_KEY));
+}
+
+#[tokio::test]
+async fn test_round_robin_takes_targets_in_declared_order() {
+    let policy = policy(json!({"strategy": "round_robin", "targets": ["security", "devops"]}));
+    let mut chosen = Vec::new();
+    for _ in 0..5 {
+        chosen.push(policy.choose(ANY_TARGET, "Anything", idle).await.target);
+    }
+    assert_eq!(chosen, ["security", "devops", "security", "devops", "security"]);
+}
+
+#[tokio::test]
+async fn test_least_loaded_breaks_ties_by_declared_order() {
+    let policy = policy(json!({"strategy": "least_loaded"}));
+    let loads = HashMap::from([("devops", 3), ("general", 1), ("security", 1)]);
+    let route = policy.choose(ANY_TARGET, "Anything", |id| loads[id]).await;
+    assert_eq!((route.target.as_str(), route.rule), ("general", None));
+
+    let route = policy.choose(ANY_TARGET, "Anything", idle).await;
+    assert_eq!(route.target, "devops");
+}
+
+#[tokio::test]
+async fn test_static_takes_the_fallback() {
+    let policy = policy(json!({"strategy": "static", "fallback": "security"}));
+    for _ in 0..3 {
+        assert_eq!(policy.choose(ANY_TARGET, "Anything", idle).await.target, "security");
+    }
+    // Without one, the first target
+    let policy = self::policy(json!({"strategy": "static", "targets": ["general", "devops"]}));
+    assert_eq!(policy.choose(ANY_TARGET, "Anything", idle).await.target, "general");
+}
+
+#[test]
+fn test_groups_must_resolve_to_a_neuron() {
+    let invalid = [
+        json!({"strategy": "content_match", "targets": ["unknown"]}),
+        json!({"strategy": "round_robin", "fallback": "unknown"}),
+        json!({"strategy": "static", "group": "devops"}),
+        json!({"strategy": "static", "group": ANY_TARGET}),
+        json!({"strategy": "random"}),
+        json!({"strategy": "round_robin", "rules": [{"name": "a", "target": "devops", "keywords": ["x"]}]}),
+        json!({"strategy": "content_match", "rules": [{"name": "a", "target": "unknown", "keywords": ["x"]}]}),
+        json!({"strategy": "content_match", "rules": [{"name": "a", "target": "devops"}]}),
+        json!({"strategy": "content_match", "rules": [{"name": "a", "target": "devops", "pattern": "("}]}),
+        json!({"strategy": "content_match", "rules": [
+            {"name": "a", "target": "devops", "examples": ["x"], "min_similarity": 0.0}
+        ]}),
+    ];
+    for routing in invalid {
+        assert!(RoutingPolicy::for_neuron(&planner(routing.clone())).is_err(), "{}", routing);
+    }
+
+    // A neuron without forward connections has nothing to route to
+    let mut terminal = planner(json!({"strategy": "round_robin"}));
+    terminal.forward_connections.clear();
+    let err = RoutingPolicy::for_neuron(&terminal).err().unwrap();
+    assert!(err.to_string().contains("resolves to no neuron"), "{}", err);
+
+    terminal.settings.clear();
+    assert!(RoutingPolicy::for_neuron(&terminal).unwrap().is_none());
+}
+
+#[test]
+fn test_forwards_naming_no_neuron_are_left_to_the_router() {
+    let config = planner(json!({"strategy": "round_robin", "group": "implementers"}));
+    let neuron = ManagedNeuron::new(config, Box::new(MockClaude::new("L4", &Default::default()))).unwrap();
+    let targets = |response: &str| -> Vec<String> {
+        let instruction = ForwardInstruction::parse(response).unwrap();
+        neuron.forward_signals(&instruction).into_iter().map(|s| s.to_neuron).collect()
+    };
+
+    assert_eq!(targets("FORWARD_TO:\nCONTENT: Build it"), ["implementers"]);
+    assert_eq!(targets("FORWARD_TO: implementers, implementers\nCONTENT: Build it"), ["implementers"]);
+    assert_eq!(targets("FORWARD_TO: security, implementers\nCONTENT: Build it"), ["security", "implementers"]);
+    assert!(targets("FORWARD_TO: unknown\nCONTENT: Build it").is_empty());
+
+    // Without a group they are addressed to any target
+    let config = planner(json!({"strategy": "round_robin"}));
+    let neuron = ManagedNeuron::new(config, Box::new(MockClaude::new("L4", &Default::default()))).unwrap();
+    let instruction = ForwardInstruction::parse("FORWARD_TO:\nCONTENT: Build it").unwrap();
+    let signals = neuron.forward_signals(&instruction);
+    assert_eq!(signals.iter().map(|s| s.to_neuron.as_str()).collect::<Vec<_>>(), [ANY_TARGET]);
+
+    // Children do not inherit the route their parent was sent on
+    let mut parent = NeuronSignal::forward("api-client", "planner", "API", "L4", "Plan it".into());
+    parent.metadata.insert(ROUTING_ROUTE_KEY.to_string(), "planner".to_string());
+    let children = neuron.parse_response("FORWARD_TO: devops\nCONTENT: Build it", &parent);
+    assert_eq!(children.len(), 1);
+    assert!(!children[0].metadata.contains_key(ROUTING_ROUTE_KEY));
+}
@@ -0,0 +1,209 @@
+//! Tests for load-based neuron cloning
+
+mod common;
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use serde_json::Value;
+
+use hal9_core::{
+    config::{ClaudeConfig, ScalingConfig},
+    Layer, NeuronConfig,
+};
+use hal9_server::{
+    claude::MockClaude,
+    neuron::{ManagedNeuron, NeuronRegistry},
+    router::RoutingTable,
+    self_organizer::{LoadSelfOrganizer, LoadTracker, NeuronFactory, ScalingAction},
+};
+
+use common::neuron_config;
+
+const SOURCE: &str = "worker";
+
+fn scaling_config() -> ScalingConfig {
+    ScalingConfig {
+        enabled: true,
+        neuron_concurrency: 2,
+        evaluation_interval_ms: 20,
+        sustained_ms: 30,
+        cooldown_ms: 60,
+        scale_up_queue_depth: 4,
+        scale_up_p95_ms: 60_000,
+        scale_down_queue_depth: 0,
+        scale_down_p95_ms: 60_000,
+        latency_window_ms: 200,
+        max_clones_per_layer: 3,
+        min_neurons_per_layer: 1,
+        max_budget_utilization: 0.9,
+        drain_timeout_ms: 1_000,
+    }
+}
+
+fn factory() -> NeuronFactory {
+    Arc::new(|config: NeuronConfig| {
+        let claude = Box::new(MockClaude::new(&config.layer, &ClaudeConfig::default()));
+        ManagedNeuron::new(config, claude)
+    })
+}
+
+struct Harness {
+    registry: Arc<NeuronRegistry>,
+    routing_table: Arc<RoutingTable>,
+    load: Arc<LoadTracker>,
+    organizer: Arc<LoadSelfOrganizer>,
+}
+
+async fn harness(config: ScalingConfig, budget_used: Option<f64>) -> Harness {
+    let registry = Arc::new(NeuronRegistry::new());
+    registry.register(factory()(neuron_config(SOURCE, "L2", &[], Value::Null)).unwrap()).await.unwrap();
+    let routing_table = Arc::new(RoutingTable::new());
+    routing_table.build_from_configs(&[neuron_config(SOURCE, "L2", &[], Value::Null)]);
+    let load = Arc::new(LoadTracker::new(
+        config.neuron_concurrency,
+        Duration::from_millis(config.latency_window_ms),
+    ));
+
+    let mut organizer = LoadSelfOrganizer::new(
+        config,
+        registry.clone(),
+        routing_table.clone(),
+        load.clone(),
+        factory(),
+    );
+    if let Some(used) = budget_used {
+        organizer.set_budget_probe(Arc::new(move || Box::pin(async move { used })));
+    }
+
+    Harness {
+        registry,
+        routing_table,
+        load,
+        organizer: Arc::new(organizer),
+    }
+}
+
+/// Send `count` signals 5ms apart, each taking 20ms, and return the p95
+/// latency in ms as seen by the senders
+async fn burst(harness: &Harness, count: usize, organize: bool) -> u128 {
+    let evaluator = organize.then(|| {
+        let organizer = harness.organizer.clone();
+        tokio::spawn(async move {
+            loop {
+                organizer.evaluate().await;
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+        })
+    });
+
+    let mut signals = Vec::new();
+    for _ in 0..count {
+        let routing_table = harness.routing_table.clone();
+        let load = harness.load.clone();
+        signals.push(tokio::spawn(async move {
+            let started = Instant::now();
+            let target = routing_table.resolve(SOURCE, |id| load.queue_depth(id));
+            let _guard = load.begin(&target).await;
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            started.elapsed().as_millis()
+        }));
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+
+    let mut latencies = Vec::new();
+    for signal in signals {
+        latencies.push(signal.await.unwrap());
+    }
+    if let Some(evaluator) = evaluator {
+        evaluator.abort();
+    }
+
+    latencies.sort_unstable();
+    latencies[(latencies.len() * 95).div_ceil(100) - 1]
+}
+
+#[tokio::test]
+async fn test_cloning_lowers_p95_under_burst() {
+    let fixed = harness(scaling_config(), None).await;
+    let fixed_p95 = burst(&fixed, 150, false).await;
+    assert_eq!(fixed.registry.by_layer(Layer::L2).len(), 1);
+
+    let organized = harness(scaling_config(), None).await;
+    let organized_p95 = burst(&organized, 150, true).await;
+
+    assert!(organized.organizer.clone_count(Layer::L2).await > 0);
+    assert!(
+        organized_p95 * 2 < fixed_p95,
+        "p95 with cloning {}ms, without {}ms",
+        organized_p95,
+        fixed_p95
+    );
+
+    let events = organized.organizer.events();
+    let scale_up = events.iter()
+        .find(|event| event.action == ScalingAction::ScaleUp)
+        .expect("scale-up event recorded");
+    assert_eq!(scale_up.layer, "L2");
+    assert_eq!(scale_up.source_neuron, SOURCE);
+    assert!(scale_up.clone_id.as_deref().unwrap().starts_with("worker-clone-"));
+    assert!(scale_up.queue_depth > 4);
+}
+
+#[tokio::test]
+async fn test_clones_capped_per_layer() {
+    let config = ScalingConfig {
+        max_clones_per_layer: 1,
+        ..scaling_config()
+    };
+    let harness = harness(config, None).await;
+    burst(&harness, 150, true).await;
+
+    assert_eq!(harness.organizer.clone_count(Layer::L2).await, 1);
+    assert_eq!(harness.routing_table.siblings(SOURCE).len(), 2);
+    let events = harness.organizer.events();
+    assert!(events.iter().any(|event| {
+        event.action == ScalingAction::ScaleUpSkipped && event.reason.contains("maximum of 1 clones")
+    }));
+}
+
+#[tokio::test]
+async fn test_no_cloning_when_budget_nearly_spent() {
+    let harness = harness(scaling_config(), Some(0.95)).await;
+    burst(&harness, 100, true).await;
+
+    assert_eq!(harness.organizer.clone_count(Layer::L2).await, 0);
+    assert_eq!(harness.registry.by_layer(Layer::L2).len(), 1);
+    let events = harness.organizer.events();
+    assert!(!events.is_empty());
+    assert!(events.iter().all(|event| event.action == ScalingAction::ScaleUpSkipped));
+    assert!(events[0].reason.contains("cost budget"));
+}
+
+#[tokio::test]
+async fn test_clones_removed_once_load_subsides() {
+    let harness = harness(scaling_config(), None).await;
+    burst(&harness, 150, true).await;
+    assert!(harness.registry.by_layer(Layer::L2).len() > 1);
+
+    // Idle layer: latencies age out of the window, then clones drain one by one
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while harness.registry.by_layer(Layer::L2).len() > 1 && Instant::now() < deadline {
+        harness.organizer.evaluate().await;
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+
+    let remaining = harness.registry.by_layer(Layer::L2);
+    assert_eq!(remaining.len(), 1);
+    assert_eq!(remaining[0].id, SOURCE);
+    assert_eq!(harness.routing_table.siblings(SOURCE), vec![SOURCE.to_string()]);
+    assert_eq!(harness.organizer.clone_count(Layer::L2).await, 0);
+
+    let scale_ups = harness.organizer.events().iter()
+        .filter(|event| event.action == ScalingAction::ScaleUp)
+        .count();
+    let scale_downs = harness.organizer.events().iter()
+        .filter(|event| event.action == ScalingAction::ScaleDown)
+        .count();
+    assert_eq!(scale_ups, scale_downs);
+}
@@ -0,0 +1,249 @@
+//! Sessions: a chain's prompt carries the summary of the turns before it,
+//! sessions expire once left unused, and summaries over their cap are
+//! compacted by the summary model
+
+mod common;
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use serde_json::Value;
+
+use hal9_core::config::{DatabaseConfig, SessionsConfig};
+use hal9_core::{NeuronInterface, NeuronSignal, Result};
+use hal9_server::chain_limits::ChainOwner;
+use hal9_server::claude::{ClaudeInterface, TokenUsage};
+use hal9_server::database_migrations;
+use hal9_server::error::ServerError;
+use hal9_server::neuron::ManagedNeuron;
+use hal9_server::prompt_composition::Tokenizer;
+use hal9_server::sessions::{self, SessionManager, SessionStore, SESSION_CONTEXT_KEY, SESSION_ID_KEY};
+
+use common::{neuron_config, ScriptedProvider};
+
+/// Answers `update` to summary updates and `condensed` to compactions,
+/// keeping the prompts it was sent
+struct Summarizer {
+    update: String,
+    condensed: &'static str,
+    prompts: Arc<Mutex<Vec<String>>>,
+}
+
+#[async_trait]
+impl ClaudeInterface for Summarizer {
+    async fn send_message(&self, message: &str) -> Result<String> {
+        self.prompts.lock().unwrap().push(message.to_string());
+        if message.starts_with("Condense") {
+            Ok(self.condensed.to_string())
+        } else {
+            Ok(self.update.clone())
+        }
+    }
+
+    fn system_prompt(&self) -> &str {
+        ""
+    }
+
+    fn last_token_usage(&self) -> Option<TokenUsage> {
+        None
+    }
+}
+
+fn config() -> SessionsConfig {
+    SessionsConfig { enabled: true, ..SessionsConfig::default() }
+}
+
+fn database(dir: &tempfile::TempDir) -> DatabaseConfig {
+    DatabaseConfig {
+        url: Some(format!("sqlite:{}?mode=rwc", dir.path().join("sessions.db").display())),
+        ..DatabaseConfig::default()
+    }
+}
+
+/// Sessions in a fresh, migrated SQLite database, with the prompts their
+/// summary model is sent
+async fn manager(
+    dir: &tempfile::TempDir,
+    config: SessionsConfig,
+    update: &str,
+    condensed: &'static str,
+) -> (SessionManager, Arc<Mutex<Vec<String>>>) {
+    let database = database(dir);
+    sessions::validate(&config, &database).unwrap();
+    let pool = database_migrations::connect(&database).await.unwrap().unwrap();
+    database_migrations::prepare(&pool, &database).await.unwrap();
+    let prompts = Arc::new(Mutex::new(Vec::new()));
+    let summarizer = Summarizer { update: update.to_string(), condensed, prompts: prompts.clone() };
+    let manager = SessionManager::new(config, SessionStore::new(pool), Box::new(summarizer), Tokenizer::Cl100kBase);
+    (manager, prompts)
+}
+
+fn owner(user_id: &str, org_id: Option<&str>) -> ChainOwner {
+    ChainOwner {
+        user_id: user_id.to_string(),
+        role: "user".to_string(),
+        org_id: org_id.map(str::to_string),
+        api_key_id: None,
+    }
+}
+
+/// Root signal of a chain submitted in `session`
+fn turn(session: &str, content: &str) -> NeuronSignal {
+    let mut signal = NeuronSignal::forward("api-client", "planner", "API", "L4", content.to_string());
+    signal.metadata.insert(SESSION_ID_KEY.to_string(), session.to_string());
+    signal
+}
+
+const SUMMARY: &str = "The user is planning four days in Lisbon in May on a mid-range budget.";
+
+#[tokio::test]
+async fn test_second_chain_prompt_contains_first_chains_summary() {
+    let dir = tempfile::tempdir().unwrap();
+    let (sessions, summary_prompts) = manager(&dir, config(), SUMMARY, "").await;
+    let alice = owner("alice", Some("acme"));
+    let session = sessions.create(Some(&alice)).await.unwrap();
+
+    let provider = ScriptedProvider::new("RESULT: Day 1 Alfama, day 2 Belem");
+    let prompts = provider.prompts.clone();
+    let neuron = ManagedNeuron::new(neuron_config("planner", "L4", &[], Value::Null), Box::new(provider)).unwrap();
+
+    // The first turn has no conversation before it
+    let mut first = turn(&session.id, "Plan four days in Lisbon");
+    sessions.prepare(Some(&alice), &mut first).await.unwrap();
+    assert!(!first.metadata.contains_key(SESSION_CONTEXT_KEY));
+    let output = neuron.process_signal(&first).await.unwrap();
+    let recorded = sessions.record_turn(&session.id, "chain-1", "Plan four days in Lisbon", &output).await.unwrap().unwrap();
+    assert_eq!((recorded.turns, recorded.summary.as_str()), (1, SUMMARY));
+    let summary_prompt = summary_prompts.lock().unwrap()[0].clone();
+    assert!(summary_prompt.contains("User: Plan four days in Lisbon"), "{}", summary_prompt);
+    assert!(summary_prompt.contains("Day 1 Alfama"), "{}", summary_prompt);
+
+    // The second's prompt carries the summary and the first turn
+    let mut second = turn(&session.id, "Swap day 2 for Sintra");
+    sessions.prepare(Some(&alice), &mut second).await.unwrap();
+    neuron.process_signal(&second).await.unwrap();
+    let prompts = prompts.lock().unwrap().clone();
+    assert!(!prompts[0].contains("CONVERSATION SO FAR"));
+    assert!(prompts[1].contains(&format!("CONVERSATION SO FAR:\nSummary: {}\n", SUMMARY)), "{}", prompts[1]);
+    assert!(prompts[1].contains("[Turn 1]\nUser: Plan four days in Lisbon\nResult: "), "{}", prompts[1]);
+
+    // Only members of the organization submit in it
+    let mut outsider = turn(&session.id, "What did they plan?");
+    let refused = sessions.prepare(Some(&owner("mallory", Some("other"))), &mut outsider).await;
+    assert!(matches!(refused, Err(ServerError::NotFound(_))));
+    let mut colleague = turn(&session.id, "Add a day trip");
+    sessions.prepare(Some(&owner("bob", Some("acme"))), &mut colleague).await.unwrap();
+    assert!(colleague.metadata[SESSION_CONTEXT_KEY].contains(SUMMARY));
+    assert!(sessions.sessions(Some(&owner("mallory", None))).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_sessions_expire_after_their_ttl_unless_used() {
+    let dir = tempfile::tempdir().unwrap();
+    let (sessions, _) = manager(&dir, SessionsConfig { ttl_secs: 1, ..config() }, SUMMARY, "").await;
+    let idle = sessions.create(None).await.unwrap();
+    let used = sessions.create(None).await.unwrap();
+    assert_eq!(idle.expires_at - idle.last_active_at, chrono::Duration::seconds(1));
+
+    // Submitting a turn pushes the expiry back
+    tokio::time::sleep(Duration::from_millis(600)).await;
+    sessions.prepare(None, &mut turn(&used.id, "Still there?")).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(600)).await;
+
+    let expired = sessions.session(&idle.id, None).await.unwrap_err();
+    assert!(matches!(&expired, ServerError::NotFound(message) if message.contains("expired")), "{:?}", expired);
+    assert!(sessions.prepare(None, &mut turn(&idle.id, "Hello again")).await.is_err());
+    assert_eq!(sessions.record_turn(&idle.id, "chain-1", "Hello", "Hi").await.unwrap(), None);
+    let live: Vec<String> = sessions.sessions(None).await.unwrap().into_iter().map(|session| session.id).collect();
+    assert_eq!(live, vec![used.id.clone()]);
+
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+    assert!(sessions.sessions(None).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_summary_over_its_cap_is_compacted_and_old_turns_dropped() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = SessionsConfig { recent_turns: 1, max_turns: 2, max_summary_tokens: 40, max_context_tokens: 120, ..config() };
+    let long_summary = "The user asked about the itinerary again and again. ".repeat(20);
+    let (sessions, summary_prompts) = manager(&dir, config, &long_summary, "Lisbon trip, four days, Sintra on day 2.").await;
+    let session = sessions.create(None).await.unwrap();
+
+    for seq in 1..=3 {
+        let input = format!("Question {}", seq);
+        sessions.record_turn(&session.id, &format!("chain-{}", seq), &input, &format!("Answer {}", seq)).await.unwrap();
+    }
+
+    // Every update grew past the cap and was condensed
+    let prompts = summary_prompts.lock().unwrap().clone();
+    assert_eq!(prompts.len(), 6);
+    assert!(prompts[1].starts_with("Condense this summary of a conversation to at most 40 tokens"));
+    assert!(prompts[1].contains(long_summary.trim()));
+    assert!(prompts[2].contains("SUMMARY SO FAR:\nLisbon trip, four days, Sintra on day 2."), "{}", prompts[2]);
+
+    let view = sessions.view(&session.id, None).await.unwrap();
+    assert_eq!(view.session.summary, "Lisbon trip, four days, Sintra on day 2.");
+    assert_eq!(view.session.turns, 3);
+    let kept: Vec<u64> = view.history.iter().map(|turn| turn.seq).collect();
+    assert_eq!(kept, vec![2, 3]);
+    assert_eq!(
+        view.context,
+        "Summary: Lisbon trip, four days, Sintra on day 2.\n[Turn 3]\nUser: Question 3\nResult: Answer 3\n"
+    );
+    assert_eq!(view.context_tokens, Tokenizer::Cl100kBase.count(&view.context));
+}
+
+#[tokio::test]
+async fn test_context_is_cut_to_its_token_budget() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = SessionsConfig { recent_turns: 3, max_summary_tokens: 30, max_context_tokens: 60, ..config() };
+    let (sessions, _) = manager(&dir, config, "Short summary.", "").await;
+    let session = sessions.create(None).await.unwrap();
+    let long_answer = "A long answer with many details. ".repeat(4);
+    for seq in 1..=3 {
+        sessions.record_turn(&session.id, &format!("chain-{}", seq), &format!("Question {}", seq), &long_answer).await.unwrap();
+    }
+
+    // The oldest quoted turns are left out first
+    let view = sessions.view(&session.id, None).await.unwrap();
+    assert!(view.context_tokens <= 60, "{}", view.context_tokens);
+    assert!(view.context.starts_with("Summary: Short summary.\n[Turn 3]"), "{}", view.context);
+    assert!(!view.context.contains("[Turn 2]"));
+}
+
+#[tokio::test]
+async fn test_reset_and_delete_cascade_to_turns() {
+    let dir = tempfile::tempdir().unwrap();
+    let (sessions, _) = manager(&dir, config(), SUMMARY, "").await;
+    let alice = owner("alice", None);
+    let session = sessions.create(Some(&alice)).await.unwrap();
+    sessions.record_turn(&session.id, "chain-1", "Plan four days in Lisbon", "Day 1 Alfama").await.unwrap();
+
+    let reset = sessions.reset(&session.id, Some(&alice)).await.unwrap();
+    assert_eq!((reset.turns, reset.summary.as_str()), (0, ""));
+    let view = sessions.view(&session.id, Some(&alice)).await.unwrap();
+    assert!(view.history.is_empty());
+    assert_eq!(view.context, "");
+
+    sessions.record_turn(&session.id, "chain-2", "Plan a weekend in Porto", "Day 1 Ribeira").await.unwrap();
+    let store = SessionStore::new(database_migrations::connect(&database(&dir)).await.unwrap().unwrap());
+    assert_eq!(store.turns(&session.id).await.unwrap().len(), 1);
+    assert!(matches!(sessions.delete(&session.id, Some(&owner("bob", None))).await, Err(ServerError::NotFound(_))));
+    sessions.delete(&session.id, Some(&alice)).await.unwrap();
+    assert_eq!(store.session(&session.id).await.unwrap(), None);
+    assert!(store.turns(&session.id).await.unwrap().is_empty());
+    assert!(matches!(sessions.view(&session.id, Some(&alice)).await, Err(ServerError::NotFound(_))));
+    assert_eq!(sessions.record_turn(&session.id, "chain-3", "And Faro?", "Beaches").await.unwrap(), None);
+}
+
+#[test]
+fn test_validate_rejects_unbounded_sessions() {
+    let database = DatabaseConfig { url: Some("sqlite::memory:".to_string()), ..DatabaseConfig::default() };
+    assert!(sessions::validate(&SessionsConfig::default(), &DatabaseConfig::default()).is_ok());
+    assert!(sessions::validate(&config(), &database).is_ok());
+    assert!(sessions::validate(&config(), &DatabaseConfig::default()).is_err());
+    assert!(sessions::validate(&SessionsConfig { ttl_secs: 0, ..config() }, &database).is_err());
+    assert!(sessions::validate(&SessionsConfig { recent_turns: 30, ..config() }, &database).is_err());
+    assert!(sessions::validate(&SessionsConfig { max_summary_tokens: 5000, ..config() }, &database).is_err());
+}
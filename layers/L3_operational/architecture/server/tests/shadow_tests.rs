@@ -0,0 +1,340 @@
+//! Shadow traffic against a local staging server: production answers on its
+//! own time whatever staging does, copies are redacted, and each shadow is
+//! recorded next to its production chain
+
+mod common;
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use axum::{
+    body::Body,
+    extract::State,
+    http::{header, Request, StatusCode},
+    middleware,
+    routing::post,
+    Json, Router,
+};
+use parking_lot::Mutex;
+use serde_json::{json, Value};
+
+use hal9_api_types::{
+    ApiResponse, BatchItemResult, BatchSubmitRequest, BatchSubmitResponse, SubmitSignalRequest, SubmitSignalResponse,
+};
+use hal9_core::config::{DatabaseConfig, ShadowConfig, ShadowEndpointsConfig};
+use hal9_core::metadata_schema::keys;
+use hal9_server::database_migrations;
+use hal9_server::shadow::{self, shadow_middleware, ShadowEndpoint, ShadowMirror, ShadowStore, SHADOW_OF_KEY};
+use hal9_server::simulation::SimRng;
+
+/// Staging server answering after `delay`, counting what it was sent
+struct Staging {
+    delay: Duration,
+    received: Mutex<Vec<SubmitSignalRequest>>,
+    chains: AtomicUsize,
+}
+
+impl Staging {
+    fn start_chain(&self, request: SubmitSignalRequest) -> String {
+        self.received.lock().push(request);
+        format!("staging-{}", self.chains.fetch_add(1, Ordering::SeqCst))
+    }
+}
+
+async fn staging_signal(
+    State(staging): State<Arc<Staging>>,
+    Json(request): Json<SubmitSignalRequest>,
+) -> Json<ApiResponse<SubmitSignalResponse>> {
+    tokio::time::sleep(staging.delay).await;
+    let chain_id = staging.start_chain(request);
+    Json(ApiResponse::success(SubmitSignalResponse {
+        signal_id: chain_id.clone(),
+        chain_id,
+        message: "Signal submitted".to_string(),
+    }))
+}
+
+async fn staging_batch(
+    State(staging): State<Arc<Staging>>,
+    Json(batch): Json<BatchSubmitRequest>,
+) -> Json<ApiResponse<BatchSubmitResponse>> {
+    tokio::time::sleep(staging.delay).await;
+    let results: Vec<BatchItemResult> = batch
+        .signals
+        .into_iter()
+        .enumerate()
+        .map(|(index, request)| BatchItemResult { index, signal_id: Some(staging.start_chain(request)), error: None })
+        .collect();
+    Json(ApiResponse::success(BatchSubmitResponse { accepted: results.len(), rejected: 0, results }))
+}
+
+async fn start_staging(delay: Duration) -> (String, Arc<Staging>) {
+    let staging = Arc::new(Staging { delay, received: Mutex::new(Vec::new()), chains: AtomicUsize::new(0) });
+    let app = Router::new()
+        .route("/api/v1/signal", post(staging_signal))
+        .route("/api/v1/signals/batch", post(staging_batch))
+        .with_state(staging.clone());
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+    (format!("http://{}", addr), staging)
+}
+
+/// A staging URL nothing listens on
+async fn unreachable_staging() -> String {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    format!("http://{}", listener.local_addr().unwrap())
+}
+
+/// Production's submission endpoints, starting chains `prod-0`, `prod-1`, ...
+fn production(mirror: Arc<ShadowMirror>) -> Router {
+    let chains = Arc::new(AtomicUsize::new(0));
+    let next_chain = move || format!("prod-{}", chains.fetch_add(1, Ordering::SeqCst));
+    let single = next_chain.clone();
+    Router::new()
+        .route(
+            "/api/v1/signal",
+            post(move |Json(_): Json<SubmitSignalRequest>| {
+                let chain_id = single();
+                async move {
+                    Json(ApiResponse::success(SubmitSignalResponse {
+                        signal_id: chain_id.clone(),
+                        chain_id,
+                        message: "Signal submitted".to_string(),
+                    }))
+                }
+            }),
+        )
+        .route(
+            "/api/v1/signals/batch",
+            post(move |Json(batch): Json<BatchSubmitRequest>| {
+                let results: Vec<BatchItemResult> = (0..batch.signals.len())
+                    .map(|index| BatchItemResult { index, signal_id: Some(next_chain()), error: None })
+                    .collect();
+                async move {
+                    Json(ApiResponse::success(BatchSubmitResponse { accepted: results.len(), rejected: 0, results }))
+                }
+            }),
+        )
+        .layer(middleware::from_fn_with_state(mirror, shadow_middleware))
+}
+
+fn config(target_url: &str) -> ShadowConfig {
+    ShadowConfig {
+        enabled: true,
+        target_url: Some(target_url.to_string()),
+        sample_rate: 1.0,
+        timeout_ms: 200,
+        max_in_flight: 4,
+        node_budget: 8,
+        ..ShadowConfig::default()
+    }
+}
+
+/// A mirror recording to a fresh, migrated SQLite database
+async fn mirror(config: ShadowConfig, dir: &tempfile::TempDir) -> Arc<ShadowMirror> {
+    let database = DatabaseConfig {
+        url: Some(format!("sqlite:{}?mode=rwc", dir.path().join("shadow.db").display())),
+        ..DatabaseConfig::default()
+    };
+    shadow::validate(&config, &database).unwrap();
+    let pool = database_migrations::connect(&database).await.unwrap().unwrap();
+    database_migrations::prepare(&pool, &database).await.unwrap();
+    let mirror = Arc::new(ShadowMirror::new(config, SimRng::seeded(7)));
+    mirror.attach_store(ShadowStore::new(pool));
+    mirror
+}
+
+async fn submit(app: &Router, uri: &str, body: Value) -> (StatusCode, Value, Duration) {
+    let request = Request::builder()
+        .method("POST")
+        .uri(uri)
+        .header(header::CONTENT_TYPE, "application/json")
+        .header(header::CONTENT_LENGTH, body.to_string().len())
+        .body(Body::from(body.to_string()))
+        .unwrap();
+    let started = Instant::now();
+    let reply = common::send(app, request).await;
+    (reply.status, serde_json::from_slice(&reply.body).unwrap(), started.elapsed())
+}
+
+/// Wait for `count` shadows to have been sent or to have failed
+async fn settled(mirror: &ShadowMirror, count: u64) {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    loop {
+        let status = mirror.status();
+        if status.sent + status.failed >= count && status.in_flight == 0 {
+            return;
+        }
+        assert!(Instant::now() < deadline, "shadows still pending: {:?}", status);
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+}
+
+#[tokio::test]
+async fn test_production_latency_unaffected_when_staging_hangs_or_is_down() {
+    let (hanging, _) = start_staging(Duration::from_secs(30)).await;
+    for target in [hanging, unreachable_staging().await] {
+        let dir = tempfile::tempdir().unwrap();
+        let mirror = mirror(config(&target), &dir).await;
+        let app = production(mirror.clone());
+
+        // More submissions than slots: the rest are dropped, not queued
+        let mut chains = Vec::new();
+        for i in 0..10 {
+            let (status, body, took) = submit(&app, "/api/v1/signal", json!({"layer": "L2", "content": format!("task {}", i)})).await;
+            assert_eq!(status, StatusCode::OK);
+            assert_eq!(body["data"]["chain_id"], format!("prod-{}", i));
+            assert!(took < Duration::from_millis(100), "production took {:?} with staging at {}", took, target);
+            chains.push(format!("prod-{}", i));
+        }
+
+        // Each shadow gives up within its timeout
+        let status = mirror.status();
+        assert_eq!(status.sent, 0);
+        settled(&mirror, 10 - status.dropped).await;
+        let status = mirror.status();
+        assert_eq!(status.sent, 0);
+        assert_eq!(status.failed + status.dropped, 10);
+
+        for chain in chains {
+            if let Some(record) = mirror.record(&chain).await.unwrap() {
+                assert_eq!(record.staging_chain_id, None);
+                assert!(record.error.is_some());
+                assert!(record.latency_ms < 1_000, "shadow of {} took {} ms", chain, record.latency_ms);
+            }
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_shadows_are_redacted_tagged_and_capped() {
+    let (target, staging) = start_staging(Duration::ZERO).await;
+    let dir = tempfile::tempdir().unwrap();
+    let mirror = mirror(config(&target), &dir).await;
+    let app = production(mirror.clone());
+
+    let submission = json!({
+        "layer": "L2",
+        "content": "Email jane.doe@example.com or call +1 415 555 0134 about the refund",
+        "metadata": {
+            (keys::auth::USER_ID): "jane",
+            (keys::auth::API_KEY_ID): "key-1",
+            (keys::fan_out::BUDGET): "100",
+            "note": "reply to jane.doe@example.com",
+        },
+        "tags": {"project": "support"},
+    });
+    let (status, body, _) = submit(&app, "/api/v1/signal", submission).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["data"]["chain_id"], "prod-0");
+    settled(&mirror, 1).await;
+
+    let received = staging.received.lock().clone();
+    assert_eq!(received.len(), 1);
+    let shadow = &received[0];
+    assert_eq!(shadow.content, "Email [EMAIL] or call [PHONE] about the refund");
+    assert_eq!(shadow.metadata["note"], "reply to [EMAIL]");
+    assert!(!shadow.metadata.contains_key(keys::auth::USER_ID));
+    assert!(!shadow.metadata.contains_key(keys::auth::API_KEY_ID));
+    assert_eq!(shadow.metadata[keys::fan_out::BUDGET], "8");
+    assert_eq!(shadow.metadata[SHADOW_OF_KEY], "prod-0");
+    assert_eq!(shadow.tags["project"], "support");
+    assert_eq!(shadow.tags["shadow"], "production");
+
+    // A smaller budget is kept
+    let submission = json!({"layer": "L2", "content": "hi", "metadata": {(keys::fan_out::BUDGET): "3"}});
+    submit(&app, "/api/v1/signal", submission).await;
+    settled(&mirror, 2).await;
+    assert_eq!(staging.received.lock()[1].metadata[keys::fan_out::BUDGET], "3");
+}
+
+#[tokio::test]
+async fn test_staging_chains_recorded_with_production_chains() {
+    let (target, staging) = start_staging(Duration::ZERO).await;
+    let dir = tempfile::tempdir().unwrap();
+    let mut config = config(&target);
+    config.endpoints = ShadowEndpointsConfig { signal: true, signals_batch: true };
+    let mirror = mirror(config, &dir).await;
+    let app = production(mirror.clone());
+
+    submit(&app, "/api/v1/signal", json!({"layer": "L2", "content": "one"})).await;
+    settled(&mirror, 1).await;
+    let record = mirror.record("prod-0").await.unwrap().unwrap();
+    assert_eq!(record.staging_chain_id.as_deref(), Some("staging-0"));
+    assert_eq!(record.endpoint, ShadowEndpoint::Signal);
+    assert_eq!(record.error, None);
+
+    let batch = json!({"signals": [{"layer": "L2", "content": "two"}, {"layer": "L3", "content": "three"}]});
+    let (_, body, _) = submit(&app, "/api/v1/signals/batch", batch).await;
+    assert_eq!(body["data"]["accepted"], 2);
+    settled(&mirror, 3).await;
+    let shadows: HashMap<String, String> = staging
+        .received
+        .lock()
+        .iter()
+        .map(|request| (request.content.clone(), request.metadata[SHADOW_OF_KEY].clone()))
+        .collect();
+    assert_eq!(shadows["two"], "prod-1");
+    assert_eq!(shadows["three"], "prod-2");
+    for production in ["prod-1", "prod-2"] {
+        let record = mirror.record(production).await.unwrap().unwrap();
+        assert_eq!(record.endpoint, ShadowEndpoint::SignalsBatch);
+        let staging_chain = record.staging_chain_id.unwrap();
+        assert!(staging_chain.starts_with("staging-"), "{}", staging_chain);
+    }
+    assert!(mirror.record("prod-9").await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn test_off_switch_and_endpoint_flags() {
+    let (target, staging) = start_staging(Duration::ZERO).await;
+    let dir = tempfile::tempdir().unwrap();
+    let mirror = mirror(config(&target), &dir).await;
+    let app = production(mirror.clone());
+
+    // Batches are not shadowed by default
+    let batch = json!({"signals": [{"layer": "L2", "content": "batched"}]});
+    let (status, _, _) = submit(&app, "/api/v1/signals/batch", batch).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let switched = mirror.set_active(false, Some("oncall"));
+    assert!(switched.enabled);
+    assert!(!switched.active);
+    assert_eq!(switched.last_switch.unwrap().by.as_deref(), Some("oncall"));
+    let (status, _, _) = submit(&app, "/api/v1/signal", json!({"layer": "L2", "content": "while off"})).await;
+    assert_eq!(status, StatusCode::OK);
+
+    mirror.set_active(true, Some("oncall"));
+    submit(&app, "/api/v1/signal", json!({"layer": "L2", "content": "back on"})).await;
+    settled(&mirror, 1).await;
+
+    let received: Vec<String> = staging.received.lock().iter().map(|request| request.content.clone()).collect();
+    assert_eq!(received, vec!["back on".to_string()]);
+    assert!(mirror.record("prod-0").await.unwrap().is_none());
+    assert!(mirror.record("prod-1").await.unwrap().is_none());
+    assert!(mirror.record("prod-2").await.unwrap().is_some());
+}
+
+#[test]
+fn test_validate_rejects_incomplete_config() {
+    let database = DatabaseConfig { url: Some("sqlite::memory:".to_string()), ..DatabaseConfig::default() };
+    assert!(shadow::validate(&ShadowConfig::default(), &DatabaseConfig::default()).is_ok());
+
+    let invalid = [
+        ShadowConfig { target_url: None, ..config("http://staging") },
+        ShadowConfig { target_url: Some("staging:8080".to_string()), ..config("http://staging") },
+        ShadowConfig { sample_rate: 1.5, ..config("http://staging") },
+        ShadowConfig { max_in_flight: 0, ..config("http://staging") },
+        ShadowConfig { tags: HashMap::from([("shadow".to_string(), "not ok".to_string())]), ..config("http://staging") },
+    ];
+    for config in invalid {
+        let error = shadow::validate(&config, &database).unwrap_err().to_string();
+        assert!(error.contains("Invalid shadow config"), "{}", error);
+    }
+    assert!(shadow::validate(&config("http://staging"), &DatabaseConfig::default()).is_err());
+}
@@ -0,0 +1,301 @@
+//! Signal schema versions: fixtures written by every release still read,
+//! downgrades for older readers, negotiation with peers and a fuzzed
+//! deserialization path
+//!
+//! A change to `NeuronSignal` that breaks reading an old fixture fails here.
+//! Fixtures are never edited; a new version adds one.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use proptest::prelude::*;
+use serde_json::{json, Value};
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpStream;
+
+use hal9_core::metadata_schema::keys;
+use hal9_core::signal_schema::{self, CURRENT_SCHEMA_VERSION, MIN_SCHEMA_VERSION};
+use hal9_core::{NeuronSignal, PropagationType};
+use hal9_server::network::protocol::{peer_signal_schemas, signal_schema_capability};
+use hal9_server::network::{MessageCodec, NetworkMessage, TcpTransport, TransportConfig};
+
+/// Signals as each schema version wrote them
+const FIXTURES: &[(u16, &str)] = &[
+    (1, include_str!("fixtures/signals/v1.json")),
+    (2, include_str!("fixtures/signals/v2.json")),
+    (3, include_str!("fixtures/signals/v3.json")),
+];
+
+fn fixture(version: u16) -> Vec<Value> {
+    let (_, text) = FIXTURES.iter().find(|(v, _)| *v == version).expect("fixture for every version");
+    serde_json::from_str(text).unwrap()
+}
+
+fn read(value: &Value) -> NeuronSignal {
+    serde_json::from_value(value.clone()).unwrap_or_else(|e| panic!("fixture no longer reads: {}\n{}", e, value))
+}
+
+fn metadata(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+    pairs.iter().map(|(key, value)| (key.to_string(), value.to_string())).collect()
+}
+
+/// A signal with metadata in every form the versions differ in
+fn current_signal() -> NeuronSignal {
+    let mut signal = NeuronSignal::forward("strategic-1", "tactical-1", "L4", "L3", "Plan".to_string());
+    signal.metadata = metadata(&[
+        (keys::trace::CHAIN_ID, "chain-1"),
+        (keys::auth::USER_ID, "alice"),
+        (keys::network::HOP_COUNT, "2"),
+        (keys::network::PRIORITY, "high"),
+        (keys::cost::TAGS, "project=checkout"),
+    ]);
+    signal
+}
+
+#[test]
+fn test_every_supported_version_has_a_fixture() {
+    let versions: Vec<u16> = FIXTURES.iter().map(|(version, _)| *version).collect();
+    let supported: Vec<u16> = signal_schema::supported().collect();
+    assert_eq!(versions, supported, "add a fixture for a new schema version; never drop one still read");
+}
+
+#[test]
+fn test_version_1_fixture_reads() {
+    let signals: Vec<NeuronSignal> = fixture(1).iter().map(read).collect();
+
+    let forward = &signals[0];
+    assert_eq!(forward.schema_version, CURRENT_SCHEMA_VERSION);
+    assert_eq!(forward.signal_id.to_string(), "6f1c2a9e-3b7d-4c51-9a0e-2d8f4b6c1a01");
+    assert_eq!(forward.propagation_type, PropagationType::Forward);
+    assert_eq!(forward.payload.activation.content, "Plan the checkout redesign");
+    assert_eq!(forward.payload.activation.features["urgency"], 0.5);
+    // Ad-hoc keys come back namespaced
+    assert_eq!(
+        forward.metadata,
+        metadata(&[
+            (keys::trace::CHAIN_ID, "6f1c2a9e-3b7d-4c51-9a0e-2d8f4b6c1a01"),
+            (keys::auth::USER_ID, "alice"),
+            (keys::network::FROM_SERVER, "hal9-eu-1"),
+            (keys::network::HOP_COUNT, "1"),
+            (keys::routing::IS_QUERY, "true"),
+        ])
+    );
+
+    let backward = &signals[1];
+    assert_eq!(backward.propagation_type, PropagationType::Backward);
+    let gradient = backward.payload.gradient.as_ref().unwrap();
+    assert_eq!(gradient.error_type, "validation");
+    assert_eq!(gradient.adjustments, vec!["Cite the payment provider"]);
+    assert_eq!(backward.metadata[keys::validation::STATUS], "failed");
+    assert_eq!(backward.metadata[keys::validation::REASON], "missing citation");
+}
+
+#[test]
+fn test_version_2_fixture_reads() {
+    let signal = read(&fixture(2)[0]);
+    assert_eq!(signal.schema_version, CURRENT_SCHEMA_VERSION);
+    assert_eq!(signal.metadata[keys::trace::ID], "trace-42");
+    assert_eq!(signal.metadata[keys::auth::ORG_ID], "acme");
+    assert_eq!(signal.metadata[keys::network::PRIORITY], "high");
+    assert_eq!(signal.metadata[keys::routing::DEADLINE], "2026-03-10T14:35:00Z");
+    assert_eq!(signal.metadata[keys::cost::TAGS], "project=checkout,team=growth");
+}
+
+#[test]
+fn test_version_3_fixture_reads_and_writes_back_unchanged() {
+    let value = &fixture(3)[0];
+    let signal = read(value);
+    assert_eq!(signal.metadata[keys::trace::ID], "trace-43");
+    assert_eq!(&serde_json::to_value(&signal).unwrap(), value);
+}
+
+#[test]
+fn test_unsupported_versions_are_refused() {
+    let mut value = fixture(CURRENT_SCHEMA_VERSION)[0].clone();
+    value["schema_version"] = json!(CURRENT_SCHEMA_VERSION + 1);
+    let err = serde_json::from_value::<NeuronSignal>(value.clone()).unwrap_err();
+    assert!(err.to_string().contains("newer than this server reads"), "{}", err);
+
+    value["schema_version"] = json!(MIN_SCHEMA_VERSION - 1);
+    let err = serde_json::from_value::<NeuronSignal>(value).unwrap_err();
+    assert!(err.to_string().contains("older than this server reads"), "{}", err);
+
+    assert!(signal_schema::downgrade(&current_signal(), CURRENT_SCHEMA_VERSION + 1).is_err());
+}
+
+#[test]
+fn test_downgrade_writes_what_older_releases_read() {
+    let signal = current_signal();
+    let written = serde_json::to_value(&signal).unwrap();
+    assert_eq!(written["schema_version"], CURRENT_SCHEMA_VERSION);
+
+    // Version 2 drops the version field; version 1 also the namespaced keys
+    let v2 = serde_json::to_value(signal_schema::downgrade(&signal, 2).unwrap()).unwrap();
+    assert!(v2.get("schema_version").is_none());
+    assert_eq!(v2["metadata"], written["metadata"]);
+    let v1 = serde_json::to_value(signal_schema::downgrade(&signal, 1).unwrap()).unwrap();
+    assert!(v1.get("schema_version").is_none());
+    assert_eq!(
+        v1["metadata"],
+        json!({"chain_id": "chain-1", "user_id": "alice", "hop_count": "2", "network.priority": "high", "cost.tags": "project=checkout"})
+    );
+
+    // Every version reads back as the signal it was written from
+    for version in signal_schema::supported() {
+        let downgraded = signal_schema::downgrade(&signal, version).unwrap();
+        let read: NeuronSignal = serde_json::from_value(serde_json::to_value(&downgraded).unwrap()).unwrap();
+        assert_eq!(serde_json::to_value(&read).unwrap(), written, "version {}", version);
+    }
+}
+
+#[test]
+fn test_peers_agree_on_highest_common_version() {
+    assert_eq!(signal_schema::negotiate(1..=3, 1..=3), Some(3));
+    assert_eq!(signal_schema::negotiate(1..=3, 2..=5), Some(3));
+    assert_eq!(signal_schema::negotiate(2..=4, 1..=3), Some(3));
+    assert_eq!(signal_schema::negotiate(1..=3, 1..=1), Some(1));
+    assert_eq!(signal_schema::negotiate(2..=3, 1..=1), None);
+    assert_eq!(signal_schema::negotiate(1..=3, 4..=6), None);
+
+    let ours = signal_schema_capability();
+    assert_eq!(ours, format!("signal_schema:{}-{}", MIN_SCHEMA_VERSION, CURRENT_SCHEMA_VERSION));
+    let capabilities = vec!["signal".to_string(), "signal_schema:2-7".to_string()];
+    assert_eq!(peer_signal_schemas(&capabilities), 2..=7);
+    // Peers from before versioning announce nothing and read version 1
+    assert_eq!(peer_signal_schemas(&["signal".to_string()]), 1..=1);
+    assert_eq!(peer_signal_schemas(&["signal_schema:x".to_string()]), 1..=1);
+}
+
+/// A peer speaking the raw protocol, announcing `capabilities`
+async fn raw_peer(server: &TcpTransport, capabilities: Vec<String>) -> (TcpStream, NetworkMessage) {
+    let mut stream = TcpStream::connect(server.local_addr().unwrap()).await.unwrap();
+    let hello = NetworkMessage::Hello { version: "1.0".to_string(), server_id: "server-old".to_string(), capabilities };
+    stream.write_all(&MessageCodec::encode(&hello).unwrap()).await.unwrap();
+    let reply = MessageCodec::read_frame(&mut stream, 65536).await.unwrap();
+    (stream, MessageCodec::decode(&reply).unwrap())
+}
+
+async fn start() -> TcpTransport {
+    let config = TransportConfig { bind_address: "127.0.0.1:0".parse().unwrap(), ..Default::default() };
+    let mut transport = TcpTransport::new(config, "server-b".to_string());
+    transport.start().await.unwrap();
+    transport
+}
+
+#[tokio::test]
+async fn test_unversioned_peer_gets_version_1_signals() {
+    let server = start().await;
+    let (mut legacy, hello) = raw_peer(&server, vec!["signal".to_string(), "metrics".to_string()]).await;
+    match hello {
+        NetworkMessage::Hello { capabilities, .. } => assert!(capabilities.contains(&signal_schema_capability())),
+        other => panic!("expected a hello, got {:?}", other),
+    }
+    tokio::time::timeout(Duration::from_secs(2), async {
+        while !server.is_connected("server-old") {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("legacy peer not registered");
+
+    server.send_signal("server-old", current_signal()).await.unwrap();
+
+    let frame = tokio::time::timeout(Duration::from_secs(2), MessageCodec::read_frame(&mut legacy, 65536))
+        .await
+        .expect("signal not sent")
+        .unwrap();
+    let body: Value = serde_json::from_slice(&frame[4..]).unwrap();
+    assert_eq!(body["type"], "Signal");
+    assert!(body.get("schema_version").is_none(), "{}", body);
+    assert_eq!(body["metadata"]["chain_id"], "chain-1");
+    assert!(body["metadata"].get(keys::trace::CHAIN_ID).is_none());
+}
+
+#[tokio::test]
+async fn test_peer_without_common_version_is_refused() {
+    let server = start().await;
+    let newer = format!("signal_schema:{}-{}", CURRENT_SCHEMA_VERSION + 1, CURRENT_SCHEMA_VERSION + 2);
+    let mut client = TcpTransport::new(
+        TransportConfig { bind_address: "127.0.0.1:0".parse().unwrap(), ..Default::default() },
+        "server-a".to_string(),
+    );
+    client.start().await.unwrap();
+    client.connect(server.local_addr().unwrap(), "server-b").await.unwrap();
+    assert!(client.is_connected("server-b"));
+
+    // A peer reading only newer versions never gets registered
+    let (_stream, _) = raw_peer(&server, vec!["signal".to_string(), newer]).await;
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert!(!server.is_connected("server-old"));
+}
+
+fn metadata_strategy() -> impl Strategy<Value = HashMap<String, String>> {
+    prop::collection::hash_map("[a-z]{1,8}\\.[a-z_]{1,12}", "\\PC{0,40}", 0..8)
+}
+
+proptest! {
+    /// Arbitrary bytes never panic the decoder, as signals or as frames
+    #[test]
+    fn fuzz_arbitrary_bytes_never_panic(bytes in prop::collection::vec(any::<u8>(), 0..512)) {
+        let _ = serde_json::from_slice::<NeuronSignal>(&bytes);
+        let mut frame = (bytes.len() as u32).to_be_bytes().to_vec();
+        frame.extend_from_slice(&bytes);
+        let _ = MessageCodec::decode(&frame);
+    }
+
+    /// Fixtures with a field replaced by an arbitrary JSON value either read
+    /// or fail cleanly
+    #[test]
+    fn fuzz_mutated_fixtures_fail_cleanly(
+        version in MIN_SCHEMA_VERSION..=CURRENT_SCHEMA_VERSION,
+        field in prop::sample::select(vec![
+            "schema_version", "signal_id", "propagation_type", "timestamp", "payload", "metadata", "layer_to",
+        ]),
+        replacement in prop_oneof![
+            Just(Value::Null),
+            any::<i64>().prop_map(Value::from),
+            any::<u16>().prop_map(Value::from),
+            "\\PC{0,20}".prop_map(Value::from),
+            Just(json!({})),
+            Just(json!([])),
+        ],
+    ) {
+        let mut value = fixture(version)[0].clone();
+        value[field] = replacement.clone();
+        match serde_json::from_value::<NeuronSignal>(value) {
+            Ok(signal) => {
+                prop_assert_eq!(signal.schema_version, CURRENT_SCHEMA_VERSION);
+                if field == "schema_version" {
+                    let read = replacement.as_u64().unwrap_or(0);
+                    prop_assert!(signal_schema::supported().contains(&(read as u16)));
+                }
+            }
+            Err(e) => prop_assert!(!e.to_string().is_empty()),
+        }
+    }
+
+    /// Any signal written at any supported version reads back unchanged
+    #[test]
+    fn fuzz_signals_round_trip_every_version(
+        content in "\\PC{0,200}",
+        strength in 0.0f32..=1.0,
+        metadata in metadata_strategy(),
+        version in MIN_SCHEMA_VERSION..=CURRENT_SCHEMA_VERSION,
+        backward in any::<bool>(),
+    ) {
+        let mut signal = NeuronSignal::forward("a", "b", "L3", "L2", content);
+        signal.payload.activation.strength = strength;
+        if backward {
+            signal.propagation_type = PropagationType::Backward;
+        }
+        signal.metadata = metadata;
+        let written = serde_json::to_value(&signal).unwrap();
+
+        let downgraded = signal_schema::downgrade(&signal, version).unwrap();
+        let frame = MessageCodec::encode(&NetworkMessage::Signal(Box::new(downgraded))).unwrap();
+        match MessageCodec::decode(&frame).unwrap() {
+            NetworkMessage::Signal(read) => prop_assert_eq!(serde_json::to_value(&*read).unwrap(), written),
+            other => prop_assert!(false, "expected a signal, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,128 @@
+//! Consciousness measurements pushed to a Gentle Singularity tracker: stage
+//! changes both ways come back as server events, and a running server
+//! measures itself on schedule
+
+mod common;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::Utc;
+use serde_json::{json, Value};
+
+use hal9_core::config::{SingularityConfig, WebhookConfig};
+use hal9_core::consciousness::ConsciousnessMetrics;
+use hal9_core::hierarchical::intelligence::SignalFlowHistory;
+use hal9_server::api::WsMessage;
+use hal9_server::event_stream::EventLog;
+use hal9_server::server::HAL9Server;
+use hal9_server::singularity::{self, SingularityBridge, STAGE_CHANGED_EVENT};
+use hal9_server::webhooks::WebhookManager;
+
+/// A fresh tracker on an ephemeral port, returning its base URL
+async fn start_tracker() -> String {
+    let app = gentle_singularity::web::router(gentle_singularity::web::AppState::new());
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    format!("http://{}", addr)
+}
+
+fn snapshot(phi: f64, coherence: f64) -> ConsciousnessMetrics {
+    ConsciousnessMetrics {
+        compression_ratio: 1.0,
+        emergence_score: 0.5,
+        coherence_level: coherence,
+        self_awareness: 0.5,
+        phi_value: phi,
+        timestamp: Utc::now(),
+    }
+}
+
+fn stage_events(events: &EventLog) -> Vec<Value> {
+    events
+        .read_after(0)
+        .events
+        .into_iter()
+        .filter_map(|stream_event| match stream_event.event {
+            WsMessage::ServerEvent { event, details } if event == STAGE_CHANGED_EVENT => {
+                Some(serde_json::from_str(&details).unwrap())
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn test_stage_changes_both_ways_become_server_events() {
+    let config = SingularityConfig { endpoint: Some(start_tracker().await), ..Default::default() };
+    let bridge = SingularityBridge::new(&config, &SignalFlowHistory::new(10)).unwrap().unwrap();
+    let events = EventLog::default();
+    let webhooks = Arc::new(WebhookManager::new(WebhookConfig::default()));
+
+    // Full integration of a coherent system reaches unity
+    let transition = bridge.push(&snapshot(2.0, 1.0)).await.unwrap().expect("stage change");
+    assert_eq!((transition.from.as_str(), transition.to.as_str()), ("Recognition", "Unity"));
+    assert!(!transition.regressed);
+    assert!((transition.level - 5.0).abs() < 1e-9);
+    singularity::announce(&transition, &events, &webhooks);
+
+    // The same reading again leaves the stage where it is
+    assert!(bridge.push(&snapshot(2.0, 1.0)).await.unwrap().is_none());
+
+    // Phi collapsing moves the stage back
+    let transition = bridge.push(&snapshot(0.0, 1.0)).await.unwrap().expect("stage change");
+    assert_eq!((transition.from.as_str(), transition.to.as_str()), ("Unity", "Denial"));
+    assert!(transition.regressed);
+    singularity::announce(&transition, &events, &webhooks);
+
+    let announced = stage_events(&events);
+    assert_eq!(announced.len(), 2);
+    assert_eq!(announced[0]["to"], "Unity");
+    assert_eq!(announced[1]["from"], "Unity");
+    assert_eq!(announced[1]["regressed"], true);
+    assert_eq!(announced[1]["phi"], 0.0);
+}
+
+#[tokio::test]
+async fn test_server_measures_and_pushes_on_schedule() {
+    let tracker = start_tracker().await;
+    let config = common::mock_config(json!({
+        "server_id": "singularity-test",
+        "neurons": [
+            {"id": "planner", "layer": "L4", "forward_connections": ["coder"], "backward_connections": []},
+            {"id": "coder", "layer": "L2", "forward_connections": [], "backward_connections": ["planner"]},
+        ],
+        "singularity": {"endpoint": tracker},
+    }));
+    let server = Arc::new(HAL9Server::new(config));
+    server.start().await.unwrap();
+
+    // The first measurement is taken at start, the next a minute later
+    let status: Value = tokio::time::timeout(Duration::from_secs(10), async {
+        loop {
+            let status: Value = reqwest::get(format!("{}/api/status", tracker)).await.unwrap().json().await.unwrap();
+            if status["current_cycle"].as_u64() > Some(0) && !server.signal_flow().consciousness().is_empty() {
+                return status;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+    })
+    .await
+    .expect("no measurement reached the tracker");
+
+    let measured = server.signal_flow().consciousness().last().cloned().unwrap();
+    let level = gentle_singularity::consciousness_level(measured.phi_value, measured.coherence_level);
+    assert!((status["current_consciousness"].as_f64().unwrap() - level).abs() < 1e-9, "{}", status);
+
+    server.shutdown().await.unwrap();
+}
+
+#[test]
+fn test_endpoint_must_be_http() {
+    let config = SingularityConfig { endpoint: Some("tracker:11111".to_string()), ..Default::default() };
+    assert!(singularity::validate(&config).is_err());
+    let config = SingularityConfig { endpoint: Some("http://127.0.0.1:11111".to_string()), interval_secs: 0, ..Default::default() };
+    assert!(singularity::validate(&config).is_err());
+    assert!(singularity::validate(&SingularityConfig::default()).is_ok());
+}
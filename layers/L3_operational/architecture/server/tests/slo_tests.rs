@@ -0,0 +1,172 @@
+//! SLO burn rates and error budgets from synthetic latency streams
+
+mod common;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, TimeZone, Utc};
+use serde_json::json;
+
+use hal9_core::config::{SloAlertConfig, SloConfig};
+use hal9_core::ServerConfig;
+use hal9_server::server::HAL9Server;
+use hal9_server::slo::{BurnSeverity, SloTracker};
+
+fn slo(name: &str, layer: Option<&str>, latency_ms: u64, objective: f64) -> SloConfig {
+    SloConfig {
+        name: name.to_string(),
+        layer: layer.map(str::to_string),
+        latency_ms,
+        objective,
+        window_secs: 24 * 3600,
+    }
+}
+
+fn t0() -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2024, 5, 1, 0, 0, 0).unwrap()
+}
+
+fn minute(n: i64) -> DateTime<Utc> {
+    t0() + chrono::Duration::minutes(n)
+}
+
+fn assert_close(actual: f64, expected: f64) {
+    assert!((actual - expected).abs() < 1e-9, "expected {}, got {}", expected, actual);
+}
+
+/// Ten L2 signals a minute for `minutes`, `bad` of them failed or slow
+fn stream(tracker: &SloTracker, minutes: std::ops::Range<i64>, bad: usize) {
+    for m in minutes {
+        for i in 0..10 {
+            let (latency, success) = match i {
+                _ if i >= bad => (Duration::from_millis(500), true),
+                0 => (Duration::from_millis(200), false),
+                _ => (Duration::from_millis(1500), true),
+            };
+            tracker.record_layer("L2", minute(m) + chrono::Duration::seconds(i as i64), latency, success);
+        }
+    }
+}
+
+#[test]
+fn test_burn_rates_match_hand_computed_values() {
+    let tracker = SloTracker::new(
+        vec![slo("l2-latency", Some("L2"), 1000, 0.99)],
+        SloAlertConfig::default(),
+    );
+    // Five clean hours, then an hour where 3 in 10 signals fail or run slow
+    stream(&tracker, 0..300, 0);
+    stream(&tracker, 300..360, 3);
+    // Other layers and chains do not count
+    tracker.record_layer("L3", minute(359), Duration::from_secs(10), false);
+    tracker.record_chain(minute(359), Duration::from_secs(10), false);
+
+    let status = &tracker.status(minute(360))[0];
+    assert_eq!((status.total, status.bad), (3600, 180));
+    // Last hour: 180 bad of 600, over the 1% allowed
+    assert_close(status.fast_burn_rate, 0.3 / 0.01);
+    // Last six hours: 180 bad of 3600
+    assert_close(status.slow_burn_rate, 0.05 / 0.01);
+    assert_close(status.compliance, 0.95);
+    assert!(!status.compliant);
+    // 5% bad against a 1% budget: spent five times over
+    assert_eq!(status.budget_remaining, 0.0);
+    assert_eq!(status.projected_exhaustion, Some(minute(360)));
+
+    // Only the fast alert's threshold (14.4) is reached; slow needs 6.0
+    let alerts = tracker.check_alerts(minute(360));
+    assert_eq!(alerts.len(), 1);
+    assert_eq!(alerts[0].severity, BurnSeverity::Fast);
+    assert_close(alerts[0].burn_rate, 30.0);
+    assert_eq!(tracker.status(minute(360))[0].alert, Some(BurnSeverity::Fast));
+    // Firing alerts are not sent again
+    assert!(tracker.check_alerts(minute(360)).is_empty());
+
+    // A clean hour re-arms the fast alert
+    stream(&tracker, 360..420, 0);
+    assert!(tracker.check_alerts(minute(420)).is_empty());
+    let status = &tracker.status(minute(420))[0];
+    assert_eq!(status.fast_burn_rate, 0.0);
+    assert_eq!(status.alert, None);
+
+    // 200 failures on top of the last hour's 590 good signals fire it
+    // again, and take the six hours since minute 61 past the slow threshold:
+    // 380 bad of 3790
+    for _ in 0..200 {
+        tracker.record_layer("L2", minute(420), Duration::from_millis(100), false);
+    }
+    let alerts = tracker.check_alerts(minute(421));
+    let fired: Vec<_> = alerts.iter().map(|alert| alert.severity).collect();
+    assert_eq!(fired, vec![BurnSeverity::Fast, BurnSeverity::Slow]);
+    assert_close(alerts[0].burn_rate, (200.0 / 790.0) / 0.01);
+    assert_close(alerts[1].burn_rate, (380.0 / 3790.0) / 0.01);
+    assert_eq!(alerts[1].window_secs, 6 * 3600);
+}
+
+#[test]
+fn test_projected_budget_exhaustion() {
+    let tracker = SloTracker::new(
+        vec![slo("chains", None, 2000, 0.9)],
+        SloAlertConfig::default(),
+    );
+    // 100 chains half an hour ago: two failed, one slow
+    for i in 0..100 {
+        let (latency, success) = match i {
+            0 | 1 => (Duration::from_millis(800), false),
+            2 => (Duration::from_millis(3000), true),
+            _ => (Duration::from_millis(1200), true),
+        };
+        tracker.record_chain(minute(330), latency, success);
+    }
+
+    let status = &tracker.status(minute(360))[0];
+    assert_eq!(status.layer, None);
+    // 3% bad against 10% allowed
+    assert_close(status.fast_burn_rate, 0.3);
+    assert_close(status.slow_burn_rate, 0.3);
+    assert_close(status.budget_remaining, 0.7);
+    assert!(status.compliant);
+    // 70% of a day's budget at 0.3x lasts 0.7 * 24h / 0.3 = 56h
+    let exhaustion = status.projected_exhaustion.unwrap();
+    let expected = minute(360) + chrono::Duration::hours(56);
+    assert!((exhaustion - expected).num_milliseconds().abs() <= 1, "{}", exhaustion);
+    assert!(tracker.check_alerts(minute(360)).is_empty());
+
+    // Once out of every window, nothing is burning
+    let status = &tracker.status(minute(330) + chrono::Duration::days(2))[0];
+    assert_eq!((status.total, status.slow_burn_rate, status.projected_exhaustion), (0, 0.0, None));
+    assert_eq!((status.compliance, status.budget_remaining), (1.0, 1.0));
+}
+
+#[tokio::test]
+async fn test_server_reports_layer_slos() {
+    let config = |objective: f64| -> ServerConfig {
+        common::mock_config(json!({
+            "server_id": "slo-test",
+            "monitoring": {
+                "enabled": false,
+                "metrics_interval": 60,
+                "log_level": "info",
+                "slos": [{"name": "l2-latency", "layer": "L2", "latency_ms": 1000, "objective": objective}],
+            },
+        }))
+    };
+
+    let err = HAL9Server::new(config(1.0)).start().await.unwrap_err();
+    assert!(err.to_string().contains("objective must be between 0 and 1"), "{}", err);
+
+    let server = Arc::new(HAL9Server::new(config(0.9)));
+    server.start().await.unwrap();
+    let metrics = server.metrics();
+    for _ in 0..3 {
+        metrics.record_latency("L2", Duration::from_millis(400));
+    }
+    metrics.record_latency("L2", Duration::from_millis(1400));
+    metrics.record_failed_latency("L2", Duration::from_millis(50));
+
+    let status = server.slo_status();
+    assert_eq!(status.len(), 1);
+    assert_eq!((status[0].total, status[0].bad, status[0].window_secs), (5, 2, 30 * 24 * 3600));
+    assert_close(status[0].fast_burn_rate, 0.4 / 0.1);
+}
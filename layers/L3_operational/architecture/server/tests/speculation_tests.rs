@@ -0,0 +1,234 @@
+//! Speculative execution: children of a complete forward instruction start
+//! from the streamed partial output, are confirmed when the final output
+//! forwards them too, and are cancelled, their cost wasted, when it revises
+//! them
+
+mod common;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use tokio::sync::{mpsc, Notify};
+
+use hal9_core::config::{SpeculationConfig, ValidationConfig, ValidatorConfig};
+use hal9_core::{NeuronInterface, NeuronSignal, Result};
+use hal9_server::chain_tracker::{ChainResult, ChainTracker};
+use hal9_server::claude::{ClaudeInterface, TokenUsage};
+use hal9_server::metrics::Metrics;
+use hal9_server::neuron::{ForwardInstruction, ManagedNeuron, NeuronRegistry};
+use hal9_server::router::{RoutingTable, SignalRouter};
+use hal9_server::speculation::{self, Speculator};
+use hal9_server::validation::ValidationPipeline;
+
+use common::neuron_config;
+
+const PLAN_A: &str = "CONTENT:\nbuild plan A\nFORWARD_TO: coder\n```rust\nfn main() {\n    let x = (1 + 2;\n}\n```\n";
+const PLAN_B: &str = "CONTENT:\nbuild plan B\nFORWARD_TO: coder\n```rust\nfn main() {\n    let x = (1 + 2);\n}\n```\n";
+
+fn usage() -> Option<TokenUsage> {
+    Some(TokenUsage { prompt_tokens: 100, completion_tokens: 50, total_tokens: 150, model: None, remapped_from: None })
+}
+
+/// Streams `streamed` a line at a time and holds the call open until the
+/// coder has started on it; later calls answer `retried`
+struct Planner {
+    streamed: String,
+    retried: String,
+    coder_started: Arc<Notify>,
+}
+
+#[async_trait]
+impl ClaudeInterface for Planner {
+    async fn send_message(&self, _message: &str) -> Result<String> {
+        Ok(self.retried.clone())
+    }
+
+    async fn stream_message(&self, _message: &str, partial: &mpsc::UnboundedSender<String>) -> Result<String> {
+        for line in self.streamed.split_inclusive('\n') {
+            let _ = partial.send(line.to_string());
+        }
+        self.coder_started.notified().await;
+        Ok(self.streamed.clone())
+    }
+
+    fn system_prompt(&self) -> &str {
+        ""
+    }
+
+    fn last_token_usage(&self) -> Option<TokenUsage> {
+        usage()
+    }
+}
+
+/// Answers with the plan it was given
+struct Coder {
+    started: Arc<Notify>,
+}
+
+#[async_trait]
+impl ClaudeInterface for Coder {
+    async fn send_message(&self, message: &str) -> Result<String> {
+        self.started.notify_one();
+        let plan = if message.contains("plan A") { "A" } else { "B" };
+        Ok(format!("implemented plan {}", plan))
+    }
+
+    fn system_prompt(&self) -> &str {
+        ""
+    }
+
+    fn last_token_usage(&self) -> Option<TokenUsage> {
+        usage()
+    }
+}
+
+fn speculator(max_wasted_per_hour: f64, metrics: &Arc<Metrics>) -> Speculator {
+    let config = SpeculationConfig { layers: vec!["L4".to_string()], max_wasted_per_hour };
+    let mut speculator = Speculator::new(&config).unwrap();
+    speculator.set_metrics(metrics.clone());
+    speculator
+}
+
+/// Run one chain from a planner streaming `streamed`, returning its result
+/// once it finishes
+async fn run_chain(
+    streamed: &str,
+    retried: &str,
+    metrics: &Arc<Metrics>,
+    registry: &Arc<NeuronRegistry>,
+) -> ChainResult {
+    let coder_started = Arc::new(Notify::new());
+    let planner_claude = Planner {
+        streamed: streamed.to_string(),
+        retried: retried.to_string(),
+        coder_started: coder_started.clone(),
+    };
+    let mut planner = ManagedNeuron::new(neuron_config("planner", "L4", &["coder"], Value::Null), Box::new(planner_claude)).unwrap();
+    // Broken code in the streamed response sends it back for a retry
+    planner.set_validation(
+        ValidationPipeline::from_config(&ValidationConfig {
+            validators: vec![ValidatorConfig::CodeSyntax { languages: vec![] }],
+            max_retries: 1,
+        })
+        .unwrap(),
+    );
+    registry.register(planner).await.unwrap();
+    let coder = ManagedNeuron::new(neuron_config("coder", "L3", &[], Value::Null), Box::new(Coder { started: coder_started })).unwrap();
+    registry.register(coder).await.unwrap();
+
+    let routing_table = Arc::new(RoutingTable::new());
+    routing_table.build_from_configs(&[neuron_config("planner", "L4", &["coder"], Value::Null), neuron_config("coder", "L3", &[], Value::Null)]);
+    let chain_tracker = Arc::new(ChainTracker::new());
+    let mut router = SignalRouter::new(registry.clone(), routing_table);
+    router.set_chain_tracker(chain_tracker.clone());
+    router.set_speculation(Arc::new(speculator(1.0, metrics)));
+    router.start().await.unwrap();
+
+    let mut finished = chain_tracker.subscribe();
+    let mut root = NeuronSignal::forward("client", "planner", "L5", "L4", "plan the parser".to_string());
+    chain_tracker.start(&mut root);
+    router.send_signal(root).await.unwrap();
+    tokio::time::timeout(Duration::from_secs(5), finished.recv())
+        .await
+        .expect("chain did not finish; the coder never started before the planner finished")
+        .unwrap()
+}
+
+fn registry(metrics: &Arc<Metrics>) -> Arc<NeuronRegistry> {
+    let mut registry = NeuronRegistry::new();
+    registry.set_metrics(metrics.clone());
+    Arc::new(registry)
+}
+
+#[test]
+fn test_partial_instruction_completes_once_its_content_is_closed() {
+    // No forward instruction yet, or its content may still grow
+    assert_eq!(ForwardInstruction::parse_partial("FORWARD_TO: coder\nCONTENT:\nbuild"), None);
+    assert_eq!(ForwardInstruction::parse_partial("FORWARD_TO: coder\nCONTENT:\nbuild it\n"), None);
+    assert_eq!(ForwardInstruction::parse_partial("CONTENT:\nbuild it\nFORWARD_TO: cod"), None);
+    assert_eq!(ForwardInstruction::parse_partial("CONTENT:\nbuild it\nBACKWARD_TO: planner\n"), None);
+
+    // Closed by the next directive line
+    let expected = ForwardInstruction { targets: vec!["coder".to_string(), "tester".to_string()], content: "build it\nfast".to_string() };
+    let partial = ForwardInstruction::parse_partial("CONTENT:\nbuild it\nfast\nFORWARD_TO: coder, tester\nBecause");
+    assert_eq!(partial.as_ref(), Some(&expected));
+    let partial = ForwardInstruction::parse_partial("FORWARD_TO: coder, tester\nCONTENT:\nbuild it\nfast\nERROR_TYPE: none\n");
+    assert_eq!(partial.as_ref(), Some(&expected));
+
+    // The finished response parses the same; without a closing directive
+    // its content runs to the end
+    assert_eq!(ForwardInstruction::parse("CONTENT:\nbuild it\nfast\nFORWARD_TO: coder, tester\nBecause").as_ref(), Some(&expected));
+    assert_eq!(ForwardInstruction::parse("FORWARD_TO: coder, tester\nCONTENT:\nbuild it\nfast").as_ref(), Some(&expected));
+    assert_eq!(ForwardInstruction::parse("RESULT: done"), None);
+}
+
+#[tokio::test]
+async fn test_revised_instruction_cancels_the_speculative_child() {
+    let metrics = Arc::new(Metrics::new());
+    let registry = registry(&metrics);
+    let result = run_chain(PLAN_A, PLAN_B, &metrics, &registry).await;
+
+    // Plan A started the coder early, the retry revised it to plan B
+    let snapshot = metrics.snapshot();
+    assert_eq!(snapshot.speculation["started"], 1);
+    assert_eq!(snapshot.speculation["cancelled"], 1);
+    assert!(!snapshot.speculation.contains_key("confirmed"));
+    // 100 prompt and 50 completion tokens at the default pricing
+    assert!((snapshot.speculation_wasted - 0.00105).abs() < 1e-9, "{}", snapshot.speculation_wasted);
+
+    // The cancelled branch is not part of the chain
+    assert_eq!(result.steps_completed, 2, "{:?}", result);
+    assert_eq!(result.final_output.as_deref(), Some("implemented plan B"));
+    assert_eq!(result.prompt_tokens, 200);
+    assert!(result.errors.is_empty(), "{:?}", result.errors);
+    assert_eq!(registry.get("coder").unwrap().health().await.unwrap().signals_processed, 2);
+}
+
+#[tokio::test]
+async fn test_unrevised_instruction_confirms_the_speculative_child() {
+    let metrics = Arc::new(Metrics::new());
+    let registry = registry(&metrics);
+    let result = run_chain(PLAN_B, PLAN_B, &metrics, &registry).await;
+
+    let snapshot = metrics.snapshot();
+    assert_eq!(snapshot.speculation["started"], 1);
+    assert_eq!(snapshot.speculation["confirmed"], 1);
+    assert_eq!(snapshot.speculation_wasted, 0.0);
+
+    // Forwarded once, by speculation
+    assert_eq!(result.steps_completed, 2, "{:?}", result);
+    assert_eq!(result.final_output.as_deref(), Some("implemented plan B"));
+    assert_eq!(registry.get("coder").unwrap().health().await.unwrap().signals_processed, 1);
+}
+
+#[test]
+fn test_wasted_budget_suspends_speculation() {
+    let metrics = Arc::new(Metrics::new());
+    let speculator = speculator(0.002, &metrics);
+    assert!(speculator.applies_to("L4"));
+    assert!(!speculator.applies_to("L3"));
+    assert!(speculator.within_budget());
+
+    speculator.record_wasted(usage().as_ref());
+    assert!(speculator.within_budget());
+    speculator.record_wasted(usage().as_ref());
+    assert!((speculator.wasted_last_hour() - 0.0021).abs() < 1e-9);
+    assert!(!speculator.within_budget());
+    assert!((metrics.snapshot().speculation_wasted - 0.0021).abs() < 1e-9);
+
+    // Nothing to speculate on without layers
+    assert!(Speculator::new(&SpeculationConfig::default()).is_none());
+}
+
+#[test]
+fn test_layers_and_budget_are_validated() {
+    let config: SpeculationConfig = serde_json::from_value(json!({"layers": ["L4", "L3"]})).unwrap();
+    assert_eq!(config.max_wasted_per_hour, 1.0);
+    assert!(speculation::validate(&config).is_ok());
+    let config = SpeculationConfig { layers: vec!["L10".to_string()], ..Default::default() };
+    assert!(speculation::validate(&config).is_err());
+    let config = SpeculationConfig { max_wasted_per_hour: -1.0, ..Default::default() };
+    assert!(speculation::validate(&config).is_err());
+}
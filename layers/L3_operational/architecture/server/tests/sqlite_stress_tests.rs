@@ -0,0 +1,109 @@
+//! Concurrent writes against one SQLite file must queue, not fail with
+//! `database is locked`
+
+use std::sync::Arc;
+
+use hal9_core::memory::{MemoryBuilder, MemoryStore, SqliteMemoryStore};
+use hal9_core::sqlite::SqliteTuning;
+use hal9_core::NeuronSignal;
+use hal9_server::database_runtime::{DatabaseType, RuntimeDatabase};
+use hal9_server::metrics::Metrics;
+
+const WRITERS: usize = 64;
+
+async fn open(path: &std::path::Path) -> (Arc<RuntimeDatabase>, Arc<SqliteMemoryStore>) {
+    let url = format!("sqlite:{}?mode=rwc", path.display());
+    let database = Arc::new(RuntimeDatabase::connect(&url, DatabaseType::Sqlite, SqliteTuning::default()).await.unwrap());
+    for table in [
+        "CREATE TABLE signals (id TEXT PRIMARY KEY, from_neuron TEXT NOT NULL, to_neuron TEXT NOT NULL, \
+         layer_from TEXT NOT NULL, layer_to TEXT NOT NULL, content TEXT NOT NULL, timestamp INTEGER NOT NULL)",
+        "CREATE TABLE metrics_aggregate (id TEXT PRIMARY KEY, metric_name TEXT NOT NULL, neuron_id TEXT, \
+         value REAL NOT NULL, labels TEXT DEFAULT '{}', timestamp INTEGER NOT NULL)",
+    ] {
+        database.write(|pool| async move { sqlx::query(table).execute(&pool).await }).await.unwrap();
+    }
+
+    // The memory store opens its own writer on the same file
+    let memory = Arc::new(SqliteMemoryStore::new(path.to_str().unwrap()).await.unwrap());
+    memory.initialize().await.unwrap();
+    (database, memory)
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 8)]
+async fn test_concurrent_signal_cost_and_memory_writes() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("stress.db");
+    let (database, memory) = open(&path).await;
+    let metrics = Metrics::new();
+    metrics.register_database_pool("runtime", database.clone());
+    metrics.register_database_pool("memory", Arc::new(memory.pools().clone()));
+
+    let mut tasks = Vec::new();
+    for i in 0..WRITERS {
+        let database = database.clone();
+        let memory = memory.clone();
+        tasks.push(tokio::spawn(async move {
+            let signal = NeuronSignal::forward("api", "neuron-l2", "L3", "L2", format!("signal {}", i));
+            database.insert_signal(&signal).await.map_err(|e| e.to_string())?;
+            database.record_cost("neuron-l2", "claude-3-haiku", 0.001 * i as f64).await.map_err(|e| e.to_string())?;
+            let entry = MemoryBuilder::new("neuron-l2".to_string(), "L2".to_string())
+                .with_content(format!("memory {}", i))
+                .build();
+            memory.store(entry).await.map_err(|e| e.to_string())?;
+            Ok::<_, String>(())
+        }));
+    }
+
+    let mut errors = Vec::new();
+    for task in tasks {
+        if let Err(e) = task.await.unwrap() {
+            errors.push(e);
+        }
+    }
+    assert!(errors.is_empty(), "writes failed: {:?}", errors);
+
+    let pool = sqlx::SqlitePool::connect(&format!("sqlite:{}", path.display())).await.unwrap();
+    for (table, expected) in [("signals", WRITERS), ("metrics_aggregate", WRITERS), ("memories", WRITERS)] {
+        let count: i64 = sqlx::query_scalar(&format!("SELECT COUNT(*) FROM {}", table)).fetch_one(&pool).await.unwrap();
+        assert_eq!(count as usize, expected, "rows in {}", table);
+    }
+
+    let snapshot = metrics.snapshot();
+    let runtime = &snapshot.database_pools["runtime"];
+    assert_eq!(runtime.writes, 2 + 2 * WRITERS as u64);
+    assert!(runtime.write_wait_ms_max >= runtime.write_wait_ms_avg);
+    assert_eq!(snapshot.database_pools["memory"].writes, WRITERS as u64);
+}
+
+#[tokio::test]
+async fn test_corrupt_database_fails_integrity_check() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("corrupt.db");
+    {
+        let (database, memory) = open(&path).await;
+        for i in 0..200 {
+            let signal = NeuronSignal::forward("api", "neuron-l2", "L3", "L2", format!("{:0>512}", i));
+            database.insert_signal(&signal).await.unwrap();
+        }
+        database
+            .write(|pool| async move { sqlx::query("PRAGMA wal_checkpoint(TRUNCATE)").execute(&pool).await })
+            .await
+            .unwrap();
+        memory.pools().close().await;
+    }
+
+    // Overwrite the middle of the file, leaving the header intact
+    let mut bytes = std::fs::read(&path).unwrap();
+    let middle = bytes.len() / 2;
+    for byte in &mut bytes[middle..middle + 4096] {
+        *byte = 0xAB;
+    }
+    std::fs::write(&path, bytes).unwrap();
+
+    let url = format!("sqlite:{}", path.display());
+    let error = match RuntimeDatabase::connect(&url, DatabaseType::Sqlite, SqliteTuning::default()).await {
+        Ok(_) => panic!("corrupt database passed its integrity check"),
+        Err(e) => e.to_string(),
+    };
+    assert!(error.contains("integrity") || error.contains("malformed"), "{}", error);
+}
@@ -0,0 +1,173 @@
+//! Signal stream over server-sent events: resume, lag and filters
+
+mod common;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::{routing::get, Router};
+use serde_json::json;
+use tokio::net::TcpListener;
+
+use hal9_core::ServerConfig;
+use hal9_server::api::WsMessage;
+use hal9_server::event_stream::{signal_stream_sse, EVENT_BUFFER};
+use hal9_server::server::HAL9Server;
+
+fn config() -> ServerConfig {
+    common::mock_config(json!({
+        "server_id": "sse-test",
+    }))
+}
+
+async fn serve() -> (Arc<HAL9Server>, String) {
+    let server = Arc::new(HAL9Server::new(config()));
+    let router = Router::new()
+        .route("/api/v1/signals/stream.sse", get(signal_stream_sse))
+        .with_state(server.clone());
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/api/v1/signals/stream.sse", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        axum::serve(listener, router).await.unwrap();
+    });
+    (server, url)
+}
+
+fn update(signal_id: u64, neuron_id: &str) -> WsMessage {
+    WsMessage::SignalUpdate {
+        signal_id: signal_id.to_string(),
+        neuron_id: neuron_id.to_string(),
+        status: "submitted".to_string(),
+    }
+}
+
+/// A received server-sent event
+#[derive(Debug)]
+struct Received {
+    event: Option<String>,
+    id: Option<u64>,
+    data: serde_json::Value,
+}
+
+/// Reads events off an open stream
+struct Stream {
+    response: reqwest::Response,
+    buffer: String,
+}
+
+impl Stream {
+    async fn open(url: &str, last_event_id: Option<u64>) -> Self {
+        let mut request = reqwest::Client::new().get(url);
+        if let Some(id) = last_event_id {
+            request = request.header("Last-Event-ID", id.to_string());
+        }
+        let response = request.send().await.unwrap();
+        assert_eq!(response.status(), 200);
+        assert_eq!(response.headers()["content-type"], "text/event-stream");
+        Self { response, buffer: String::new() }
+    }
+
+    async fn next(&mut self) -> Received {
+        loop {
+            if let Some(end) = self.buffer.find("\n\n") {
+                let block: String = self.buffer.drain(..end + 2).collect();
+                let mut received = Received { event: None, id: None, data: serde_json::Value::Null };
+                for line in block.lines() {
+                    match line.split_once(':') {
+                        Some(("event", value)) => received.event = Some(value.trim().to_string()),
+                        Some(("id", value)) => received.id = value.trim().parse().ok(),
+                        Some(("data", value)) => received.data = serde_json::from_str(value.trim()).unwrap(),
+                        _ => {}
+                    }
+                }
+                if received.data.is_null() {
+                    continue;
+                }
+                return received;
+            }
+            let chunk = tokio::time::timeout(Duration::from_secs(2), self.response.chunk())
+                .await
+                .expect("event not received")
+                .unwrap()
+                .expect("stream closed");
+            self.buffer.push_str(std::str::from_utf8(&chunk).unwrap());
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_resume_after_disconnect_has_no_gaps() {
+    let (server, url) = serve().await;
+    let mut stream = Stream::open(&url, None).await;
+    for i in 1..=3 {
+        server.broadcast_event(update(i, "coder"));
+    }
+    let mut last_id = 0;
+    for _ in 0..3 {
+        last_id = stream.next().await.id.unwrap();
+    }
+    drop(stream);
+
+    // Sent while disconnected
+    for i in 4..=10 {
+        server.broadcast_event(update(i, "coder"));
+    }
+    let mut stream = Stream::open(&url, Some(last_id)).await;
+    server.broadcast_event(update(11, "coder"));
+
+    let mut ids = Vec::new();
+    for _ in 4..=11 {
+        let received = stream.next().await;
+        assert_eq!(received.event, None);
+        assert_eq!(received.data["type"], "signal_update");
+        ids.push(received.id.unwrap());
+    }
+    assert_eq!(ids, (last_id + 1..=last_id + 8).collect::<Vec<_>>());
+}
+
+#[tokio::test]
+async fn test_resume_past_the_buffer_reports_lag() {
+    let (server, url) = serve().await;
+    server.broadcast_event(update(1, "coder"));
+    let last_id = server.event_log().last_id();
+
+    let missed = 10;
+    for i in 0..EVENT_BUFFER as u64 + missed {
+        server.broadcast_event(update(i + 2, "coder"));
+    }
+    let mut stream = Stream::open(&url, Some(last_id)).await;
+
+    let lagged = stream.next().await;
+    assert_eq!(lagged.event.as_deref(), Some("lagged"));
+    assert_eq!(lagged.data["skipped"], missed);
+    let first = stream.next().await;
+    assert_eq!(first.id, Some(last_id + missed + 1));
+}
+
+#[tokio::test]
+async fn test_filters_match_websocket_query() {
+    let (server, url) = serve().await;
+    let mut stream = Stream::open(&format!("{}?neuron_id=coder&type=signal_update", url), None).await;
+    server.broadcast_event(update(1, "strategist"));
+    server.broadcast_event(WsMessage::ServerEvent {
+        event: "draining".to_string(),
+        details: String::new(),
+    });
+    server.broadcast_event(update(2, "coder"));
+
+    let received = stream.next().await;
+    assert_eq!(received.data["neuron_id"], "coder");
+    assert_eq!(received.data["signal_id"], "2");
+}
+
+#[tokio::test]
+async fn test_invalid_last_event_id_is_rejected() {
+    let (_server, url) = serve().await;
+    let response = reqwest::Client::new()
+        .get(&url)
+        .header("Last-Event-ID", "not-a-number")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 400);
+}
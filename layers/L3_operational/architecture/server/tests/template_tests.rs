@@ -0,0 +1,303 @@
+//! Chain templates: variables are checked before anything is stored, files
+//! are inlined or linked as artifacts by size, and submissions render the
+//! version they pin
+
+mod common;
+
+use std::collections::{BTreeMap, HashMap};
+
+use chrono::{DateTime, Utc};
+use serde_json::json;
+
+use hal9_api_types::{OutputFormat, TemplateValue};
+use hal9_core::auth::Permissions;
+use hal9_core::config::{DatabaseConfig, TemplatesConfig};
+use hal9_core::metadata_schema::keys;
+use hal9_core::NeuronConfig;
+use hal9_server::auth_middleware::AuthUser;
+use hal9_server::database_migrations;
+use hal9_server::error::ServerError;
+use hal9_server::server::HAL9Server;
+use hal9_server::templates::{self, NewTemplate, TemplateLibrary, TemplateStore};
+
+fn neuron(id: &str, layer: &str) -> NeuronConfig {
+    NeuronConfig {
+        id: id.to_string(),
+        layer: layer.to_string(),
+        claude_command: "claude".to_string(),
+        system_prompt: None,
+        forward_connections: vec![],
+        backward_connections: vec![],
+        settings: HashMap::new(),
+    }
+}
+
+fn neurons() -> Vec<NeuronConfig> {
+    vec![neuron("triage-l3", "L3"), neuron("planner-l4", "L4")]
+}
+
+/// Triage of a bug report, as the API takes it
+fn triage(content: &str) -> NewTemplate {
+    serde_json::from_value(json!({
+        "name": "triage",
+        "neuron": "triage-l3",
+        "content": content,
+        "variables": [
+            {"name": "title", "type": "string"},
+            {"name": "severity", "type": "enum", "values": ["low", "high"], "default": "low"},
+            {"name": "report", "type": "file"}
+        ],
+        "tags": {"team": "support"},
+        "priority": "high",
+        "deadline_secs": 600,
+        "output_format": "markdown",
+        "allowed_roles": ["admin", "user"]
+    }))
+    .unwrap()
+}
+
+const TRIAGE: &str = "Triage {{title}} ({{severity}}):\n{{report}}";
+
+/// A library in a fresh, migrated SQLite database, inlining files of at
+/// most 64 bytes
+async fn library(dir: &tempfile::TempDir) -> TemplateLibrary {
+    let database = DatabaseConfig {
+        url: Some(format!("sqlite:{}?mode=rwc", dir.path().join("templates.db").display())),
+        ..DatabaseConfig::default()
+    };
+    let config = TemplatesConfig { enabled: true, inline_file_bytes: 64, max_file_bytes: 1024 };
+    templates::validate(&config, &database).unwrap();
+    let pool = database_migrations::connect(&database).await.unwrap().unwrap();
+    database_migrations::prepare(&pool, &database).await.unwrap();
+    TemplateLibrary::new(config, TemplateStore::new(pool))
+}
+
+fn text(value: &str) -> TemplateValue {
+    TemplateValue::Text(value.to_string())
+}
+
+fn file(name: &str, content: &str) -> TemplateValue {
+    TemplateValue::File { file: name.to_string(), content: content.to_string() }
+}
+
+fn invalid_input(error: ServerError) -> String {
+    match error {
+        ServerError::InvalidInput(message) => message,
+        other => panic!("expected invalid input, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_every_invalid_variable_is_reported() {
+    let dir = tempfile::tempdir().unwrap();
+    let library = library(&dir).await;
+    let template = library.add(triage(TRIAGE), &neurons(), Some("alice")).await.unwrap();
+
+    let values = HashMap::from([
+        ("title".to_string(), file("title.txt", "Crash")),
+        ("severity".to_string(), text("urgent")),
+        ("owner".to_string(), text("bob")),
+    ]);
+    let message = invalid_input(library.render(&template, values, &neurons(), None).await.unwrap_err());
+    assert!(message.starts_with("Invalid variables for template triage v1: "), "{}", message);
+    for expected in [
+        "owner is not a variable of the template",
+        "title takes text, not a file",
+        "severity must be one of low, high, not 'urgent'",
+        "report is required",
+    ] {
+        assert!(message.contains(expected), "{} missing from {}", expected, message);
+    }
+
+    // A file variable takes no text, and no file over the limit
+    let values = HashMap::from([("title".to_string(), text("Crash")), ("report".to_string(), text("Crashes on save"))]);
+    let message = invalid_input(library.render(&template, values, &neurons(), None).await.unwrap_err());
+    assert!(message.ends_with("report takes a file"), "{}", message);
+    let values = HashMap::from([("title".to_string(), text("Crash")), ("report".to_string(), file("bug.txt", &"x".repeat(1025)))]);
+    let message = invalid_input(library.render(&template, values, &neurons(), None).await.unwrap_err());
+    assert!(message.contains("report is a file of 1025 bytes, over the limit of 1024"), "{}", message);
+}
+
+#[tokio::test]
+async fn test_small_files_are_inlined_and_large_ones_linked() {
+    let dir = tempfile::tempdir().unwrap();
+    let library = library(&dir).await;
+    let template = library.add(triage(TRIAGE), &neurons(), None).await.unwrap();
+
+    let report = "Saving a draft crashes the editor.";
+    let values = HashMap::from([("title".to_string(), text("Crash")), ("report".to_string(), file("bug.txt", report))]);
+    let rendered = library.render(&template, values, &neurons(), None).await.unwrap();
+    assert_eq!(rendered.request.content, format!("Triage Crash (low):\n{}", report));
+    assert!(rendered.artifacts.is_empty());
+
+    let log = "ERROR editor: save failed\n".repeat(10);
+    let values = HashMap::from([("title".to_string(), text("Crash")), ("report".to_string(), file("editor.log", &log))]);
+    let rendered = library.render(&template, values, &neurons(), Some("acme")).await.unwrap();
+    assert_eq!(rendered.artifacts.len(), 1);
+    let id = &rendered.artifacts[0];
+    assert!(!rendered.request.content.contains("save failed"));
+    assert!(
+        rendered.request.content.ends_with(&format!(
+            "[File editor.log of {} bytes, stored as artifact {}: GET /api/v1/artifacts/{}]",
+            log.len(),
+            id,
+            id
+        )),
+        "{}",
+        rendered.request.content
+    );
+
+    let artifact = library.artifact(id).await.unwrap();
+    assert_eq!((artifact.name.as_str(), artifact.content.as_str()), ("editor.log", log.as_str()));
+    assert_eq!((artifact.template.as_str(), artifact.version, artifact.size_bytes), ("triage", 1, log.len() as u64));
+    assert!(matches!(library.artifact("missing").await, Err(ServerError::NotFound(_))));
+
+    // Stored under the submitter's organization, which is billed for it
+    assert_eq!(artifact.org_id.as_deref(), Some("acme"));
+    assert_eq!(library.artifact_bytes().await.unwrap(), HashMap::from([("acme".to_string(), log.len() as u64)]));
+}
+
+#[tokio::test]
+async fn test_submissions_render_the_version_they_pin() {
+    let dir = tempfile::tempdir().unwrap();
+    let library = library(&dir).await;
+    library.add(triage("v1: {{title}}\n{{report}}"), &neurons(), None).await.unwrap();
+    let values = || HashMap::from([("title".to_string(), text("Crash")), ("report".to_string(), file("bug.txt", "Boom"))]);
+
+    // Without a pin, a submission renders the latest version when it arrives
+    let latest = library.template("triage", None).await.unwrap();
+    assert_eq!(latest.version, 1);
+    library.add(triage("v2: {{title}} ({{severity}})\n{{report}}"), &neurons(), None).await.unwrap();
+    let latest = library.template("triage", None).await.unwrap();
+    assert_eq!(latest.version, 2);
+    let rendered = library.render(&latest, values(), &neurons(), None).await.unwrap();
+    assert_eq!(rendered.request.content, "v2: Crash (low)\nBoom");
+    assert_eq!(rendered.request.metadata[keys::template::VERSION], "2");
+
+    let pinned = library.template("triage", Some(1)).await.unwrap();
+    let rendered = library.render(&pinned, values(), &neurons(), None).await.unwrap();
+    assert_eq!(rendered.request.content, "v1: Crash\nBoom");
+    assert_eq!(rendered.request.metadata[keys::template::NAME], "triage");
+    assert_eq!(rendered.request.metadata[keys::template::VERSION], "1");
+
+    let versions: Vec<u32> = library.versions("triage").await.unwrap().iter().map(|t| t.version).collect();
+    assert_eq!(versions, vec![1, 2]);
+    let listed: Vec<(String, u32)> = library.templates().await.unwrap().into_iter().map(|t| (t.name, t.version)).collect();
+    assert_eq!(listed, vec![("triage".to_string(), 2)]);
+    assert!(matches!(library.template("triage", Some(3)).await, Err(ServerError::NotFound(_))));
+    assert!(matches!(library.template("migration-plan", None).await, Err(ServerError::NotFound(_))));
+    assert!(matches!(library.versions("migration-plan").await, Err(ServerError::NotFound(_))));
+}
+
+#[tokio::test]
+async fn test_rendered_submission_carries_the_template_defaults() {
+    let dir = tempfile::tempdir().unwrap();
+    let library = library(&dir).await;
+    let template = library.add(triage(TRIAGE), &neurons(), Some("alice")).await.unwrap();
+    assert_eq!(template.created_by.as_deref(), Some("alice"));
+
+    let before = Utc::now();
+    let values = HashMap::from([
+        ("title".to_string(), text("Crash")),
+        ("severity".to_string(), text("high")),
+        ("report".to_string(), file("bug.txt", "Boom")),
+    ]);
+    let request = library.render(&template, values, &neurons(), None).await.unwrap().request;
+    assert_eq!((request.layer.as_str(), request.neuron_id.as_deref()), ("L3", Some("triage-l3")));
+    assert_eq!(request.output_format, Some(OutputFormat::Markdown));
+    assert_eq!(request.tags, BTreeMap::from([("team".to_string(), "support".to_string())]));
+    assert_eq!(request.metadata[keys::network::PRIORITY], "high");
+    let deadline: DateTime<Utc> = request.metadata[keys::routing::DEADLINE].parse().unwrap();
+    let secs = (deadline - before).num_seconds();
+    assert!((599..=601).contains(&secs), "deadline {} seconds out", secs);
+
+    assert!(template.permits(Some("user")));
+    assert!(!template.permits(Some("guest")));
+    assert!(!template.permits(None));
+
+    // A template whose neuron was removed cannot be rendered
+    let message = invalid_input(library.render(&template, HashMap::new(), &[neuron("planner-l4", "L4")], None).await.unwrap_err());
+    assert!(message.contains("not running"), "{}", message);
+}
+
+#[tokio::test]
+async fn test_invalid_templates_are_refused() {
+    let dir = tempfile::tempdir().unwrap();
+    let library = library(&dir).await;
+
+    let mut unknown_neuron = triage(TRIAGE);
+    unknown_neuron.spec.neuron = "missing".to_string();
+    let mut bad_name = triage(TRIAGE);
+    bad_name.name = "bug triage".to_string();
+    let mut bad_default = triage(TRIAGE);
+    bad_default.spec.variables[1].default = Some("urgent".to_string());
+    let mut schema_without_json = triage(TRIAGE);
+    schema_without_json.spec.output_schema = Some(json!({"type": "object"}));
+    let mut bad_tags = triage(TRIAGE);
+    bad_tags.spec.tags.insert("team".to_string(), "not ok".to_string());
+
+    for template in [
+        unknown_neuron,
+        bad_name,
+        bad_default,
+        schema_without_json,
+        bad_tags,
+        triage("Triage {{title}} for {{assignee}}"),
+        triage("Triage {{title"),
+    ] {
+        invalid_input(library.add(template, &neurons(), None).await.unwrap_err());
+    }
+    assert!(library.templates().await.unwrap().is_empty());
+}
+
+#[test]
+fn test_validate_rejects_inconsistent_config() {
+    let database = DatabaseConfig { url: Some("sqlite::memory:".to_string()), ..DatabaseConfig::default() };
+    assert!(templates::validate(&TemplatesConfig::default(), &DatabaseConfig::default()).is_ok());
+
+    let enabled = TemplatesConfig { enabled: true, ..TemplatesConfig::default() };
+    assert!(templates::validate(&enabled, &database).is_ok());
+    assert!(templates::validate(&enabled, &DatabaseConfig::default()).is_err());
+    let inline_over_max = TemplatesConfig { inline_file_bytes: 2048, max_file_bytes: 1024, ..enabled.clone() };
+    assert!(templates::validate(&inline_over_max, &database).is_err());
+    let no_files = TemplatesConfig { inline_file_bytes: 0, max_file_bytes: 0, ..enabled };
+    assert!(templates::validate(&no_files, &database).is_err());
+}
+
+#[tokio::test]
+async fn test_artifacts_are_only_found_by_their_organization() {
+    let dir = tempfile::tempdir().unwrap();
+    let server = HAL9Server::new(common::mock_config(json!({
+        "auth": {"enabled": true},
+        "database": {"url": format!("sqlite:{}?mode=rwc", dir.path().join("server.db").display())},
+        "templates": {"enabled": true, "inline_file_bytes": 64},
+        "memory": {"enabled": false},
+    })));
+    server.start().await.unwrap();
+
+    let library = server.templates().await.unwrap();
+    let template = library.add(triage(TRIAGE), &neurons(), None).await.unwrap();
+    let log = "ERROR editor: save failed\n".repeat(10);
+    let values = HashMap::from([("title".to_string(), text("Crash")), ("report".to_string(), file("editor.log", &log))]);
+    let rendered = library.render(&template, values, &neurons(), Some("acme")).await.unwrap();
+    let id = &rendered.artifacts[0];
+
+    let user = |org_id: Option<&str>| AuthUser {
+        user_id: "u1".to_string(),
+        username: "ada".to_string(),
+        role: "admin".to_string(),
+        permissions: Permissions::new(),
+        org_id: org_id.map(str::to_string),
+        api_key_id: Some("k1".to_string()),
+    };
+    let artifact = server.template_artifact(Some(&user(Some("acme"))), id).await.unwrap();
+    assert_eq!(artifact.content, log);
+
+    // Another organization, or none, is told it does not exist
+    for caller in [Some(user(Some("globex"))), Some(user(None)), None] {
+        let err = server.template_artifact(caller.as_ref(), id).await.unwrap_err();
+        assert!(matches!(err, ServerError::NotFound(_)), "{:?}: {}", caller.map(|user| user.org_id), err);
+    }
+
+    server.shutdown().await.unwrap();
+}
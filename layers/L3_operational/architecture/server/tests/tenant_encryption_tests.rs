@@ -0,0 +1,196 @@
+//! Per-organization encryption at rest: organizations never read each
+//! other's values, rotated keys are moved off lazily and by the sweep, and
+//! turning encryption off keeps what was sealed readable
+
+use std::sync::Arc;
+
+use serde_json::json;
+
+use hal9_core::config::{EncryptedSearch, EncryptionConfig};
+use hal9_core::encryption::{self, TenantKeyring};
+use hal9_core::memory::{MemoryBuilder, MemoryEntry, MemorySearch, MemoryStore, SqliteMemoryStore};
+use hal9_core::metadata_schema::keys;
+use hal9_core::secrets::{MasterKey, StaticKeyProvider};
+use hal9_core::sqlite::{SqlitePools, SqliteTuning};
+use hal9_core::NeuronSignal;
+use hal9_server::database_runtime::{DatabaseType, RuntimeDatabase};
+use hal9_server::tenant_encryption;
+
+async fn keyring(master: &MasterKey, pools: SqlitePools, orgs: &[&str]) -> Arc<TenantKeyring> {
+    let orgs = orgs.iter().map(|org| org.to_string());
+    Arc::new(TenantKeyring::open(pools, &StaticKeyProvider(master.clone()), orgs).await.unwrap())
+}
+
+async fn key_store() -> SqlitePools {
+    SqlitePools::connect("sqlite::memory:", SqliteTuning::default()).await.unwrap()
+}
+
+async fn memory(keyring: Arc<TenantKeyring>, search: EncryptedSearch) -> SqliteMemoryStore {
+    let config = EncryptionConfig { search, ..Default::default() };
+    let store = SqliteMemoryStore::in_memory()
+        .await
+        .unwrap()
+        .with_keyring(keyring, tenant_encryption::sealed_search(&config, 384));
+    store.initialize().await.unwrap();
+    store
+}
+
+fn entry(org_id: &str, content: &str) -> MemoryEntry {
+    MemoryBuilder::new("coder".to_string(), "L2".to_string())
+        .with_content(content.to_string())
+        .with_metadata(json!({ keys::auth::ORG_ID: org_id }))
+        .build()
+}
+
+async fn stored_content(store: &SqliteMemoryStore, id: uuid::Uuid) -> String {
+    sqlx::query_scalar("SELECT content FROM memories WHERE id = ?")
+        .bind(id.to_string())
+        .fetch_one(store.pools().reader())
+        .await
+        .unwrap()
+}
+
+/// A signal log in a temporary file with the columns both writes and
+/// reads use
+async fn signal_log(path: &std::path::Path, keyring: Arc<TenantKeyring>) -> RuntimeDatabase {
+    let url = format!("sqlite:{}?mode=rwc", path.display());
+    let database = RuntimeDatabase::connect(&url, DatabaseType::Sqlite, SqliteTuning::default())
+        .await
+        .unwrap()
+        .with_keyring(keyring);
+    let table = "CREATE TABLE signals (id TEXT PRIMARY KEY, from_neuron TEXT NOT NULL, to_neuron TEXT NOT NULL, \
+         layer_from TEXT NOT NULL, layer_to TEXT NOT NULL, content TEXT NOT NULL, timestamp INTEGER NOT NULL)";
+    database.write(|pool| async move { sqlx::query(table).execute(&pool).await }).await.unwrap();
+    database
+}
+
+fn signal(org_id: &str, content: &str) -> NeuronSignal {
+    let mut signal = NeuronSignal::forward("planner", "coder", "L4", "L2", content.to_string());
+    signal.metadata.insert(keys::auth::ORG_ID.to_string(), org_id.to_string());
+    signal
+}
+
+#[tokio::test]
+async fn test_organizations_are_sealed_with_their_own_keys() {
+    let keyring = keyring(&MasterKey::generate(), key_store().await, &["acme", "globex"]).await;
+    let store = memory(keyring.clone(), EncryptedSearch::Embedding).await;
+
+    let acme = store.store(entry("acme", "acme launch plan")).await.unwrap();
+    let globex = store.store(entry("globex", "globex launch plan")).await.unwrap();
+    let initech = store.store(entry("initech", "initech launch plan")).await.unwrap();
+
+    // Sealed at rest under each organization's key; others stay plaintext
+    let (acme_key, globex_key) = (keyring.current_key("acme").unwrap(), keyring.current_key("globex").unwrap());
+    assert_ne!(acme_key, globex_key);
+    let acme_sealed = stored_content(&store, acme).await;
+    assert!(acme_sealed.starts_with(&encryption::sealed_prefix(&acme_key)), "{}", acme_sealed);
+    assert!(!acme_sealed.contains("launch"));
+    assert!(stored_content(&store, globex).await.starts_with(&encryption::sealed_prefix(&globex_key)));
+    assert_eq!(stored_content(&store, initech).await, "initech launch plan");
+    assert_eq!(store.get(acme).await.unwrap().unwrap().content, "acme launch plan");
+
+    // A value claiming another organization's key does not open
+    let forged = acme_sealed.replacen(&acme_key, &globex_key, 1);
+    let err = keyring.unseal(&forged).unwrap_err();
+    assert!(err.to_string().contains("wrong key or altered value"), "{}", err);
+
+    // Same for signals in the log
+    let dir = tempfile::tempdir().unwrap();
+    let log = signal_log(&dir.path().join("signals.db"), keyring.clone()).await;
+    let sent = signal("globex", "ship it");
+    log.insert_signal(&sent).await.unwrap();
+    let stored: String = log.get_signal(sent.signal_id).await.unwrap().unwrap().content;
+    assert_eq!(stored, "ship it");
+    let archived = log.signals_before(chrono::Utc::now() + chrono::Duration::days(1), 10, 0).await.unwrap();
+    assert!(archived[0].content.starts_with(&encryption::sealed_prefix(&globex_key)), "archives stay sealed");
+}
+
+#[tokio::test]
+async fn test_rotated_keys_are_resealed_on_read_and_by_the_sweep() {
+    let keyring = keyring(&MasterKey::generate(), key_store().await, &["acme"]).await;
+    let store = memory(keyring.clone(), EncryptedSearch::Embedding).await;
+    let dir = tempfile::tempdir().unwrap();
+    let log = signal_log(&dir.path().join("signals.db"), keyring.clone()).await;
+
+    let read = store.store(entry("acme", "read after rotation")).await.unwrap();
+    let swept = store.store(entry("acme", "never read")).await.unwrap();
+    let (read_signal, swept_signal) = (signal("acme", "read signal"), signal("acme", "swept signal"));
+    log.insert_signal(&read_signal).await.unwrap();
+    log.insert_signal(&swept_signal).await.unwrap();
+    let old_key = keyring.current_key("acme").unwrap();
+
+    let new_key = keyring.rotate("acme").await.unwrap();
+    assert_ne!(old_key, new_key);
+    assert_eq!(keyring.retired_keys(), vec![old_key.clone()]);
+    let status = keyring.org_status("acme");
+    assert_eq!((status.current_key.as_deref(), status.retired_keys.clone()), (Some(new_key.as_str()), vec![old_key]));
+
+    // Reading moves a value to the current key
+    assert_eq!(store.get(read).await.unwrap().unwrap().content, "read after rotation");
+    assert!(stored_content(&store, read).await.starts_with(&encryption::sealed_prefix(&new_key)));
+    assert_eq!(log.get_signal(read_signal.signal_id).await.unwrap().unwrap().content, "read signal");
+
+    // The sweep moves the rest
+    assert_eq!(store.reseal(100).await.unwrap(), 1);
+    assert_eq!(store.reseal(100).await.unwrap(), 0);
+    assert!(stored_content(&store, swept).await.starts_with(&encryption::sealed_prefix(&new_key)));
+    assert_eq!(log.reseal_signals(100).await.unwrap(), 1);
+    assert_eq!(log.reseal_signals(100).await.unwrap(), 0);
+    assert_eq!(log.get_signal(swept_signal.signal_id).await.unwrap().unwrap().content, "swept signal");
+}
+
+#[tokio::test]
+async fn test_disabling_encryption_keeps_sealed_values_readable() {
+    let master = MasterKey::generate();
+    let pools = key_store().await;
+    let first = keyring(&master, pools.clone(), &["acme"]).await;
+    let store = memory(first.clone(), EncryptedSearch::Embedding).await;
+
+    let before = store.store(entry("acme", "sealed before")).await.unwrap();
+    first.set_encrypted("acme", false);
+    let after = store.store(entry("acme", "plain after")).await.unwrap();
+    assert!(encryption::is_sealed(&stored_content(&store, before).await));
+    assert_eq!(stored_content(&store, after).await, "plain after");
+    assert_eq!(store.get(before).await.unwrap().unwrap().content, "sealed before");
+    assert!(!first.org_status("acme").encrypted);
+
+    // A restart without the organization configured still reads it
+    let reopened = keyring(&master, pools, &[]).await;
+    let sealed = stored_content(&store, before).await;
+    assert_eq!(reopened.unseal(&sealed).unwrap(), "sealed before");
+}
+
+#[tokio::test]
+async fn test_sealed_entries_match_searches_by_embedding_only_when_enabled() {
+    let keyring = keyring(&MasterKey::generate(), key_store().await, &["acme"]).await;
+    let search = MemorySearch {
+        content_query: Some("deployment checklist for production".to_string()),
+        limit: 10,
+        ..Default::default()
+    };
+
+    let store = memory(keyring.clone(), EncryptedSearch::Embedding).await;
+    store.store(entry("acme", "deployment checklist for production")).await.unwrap();
+    store.store(entry("initech", "deployment checklist for production")).await.unwrap();
+    let found = store.search(search.clone()).await.unwrap();
+    assert_eq!(found.len(), 2, "{:?}", found);
+    assert!(found.iter().all(|entry| entry.content == "deployment checklist for production"));
+
+    let store = memory(keyring, EncryptedSearch::Disabled).await;
+    store.store(entry("acme", "deployment checklist for production")).await.unwrap();
+    store.store(entry("initech", "deployment checklist for production")).await.unwrap();
+    let found = store.search(search).await.unwrap();
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].metadata[keys::auth::ORG_ID], "initech");
+}
+
+#[test]
+fn test_orgs_need_a_key_store() {
+    let config = EncryptionConfig { orgs: vec!["acme".to_string()], ..Default::default() };
+    assert!(tenant_encryption::validate(&config).is_err());
+    let config = EncryptionConfig { key_store: Some("keys.db".to_string()), search_min_similarity: 1.5, ..Default::default() };
+    assert!(tenant_encryption::validate(&config).is_err());
+    let config = EncryptionConfig { key_store: Some("keys.db".to_string()), orgs: config.orgs, ..Default::default() };
+    assert!(tenant_encryption::validate(&config).is_ok());
+    assert!(tenant_encryption::validate(&EncryptionConfig::default()).is_ok());
+}
@@ -0,0 +1,369 @@
+//! Time travel: queue depths and chains rebuilt from snapshots against the
+//! ground truth of fixture timelines, past instants over the API, and
+//! snapshots read back once archived
+
+mod common;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::{http::StatusCode, Router};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use hal9_core::config::{RetentionConfig, TimeTravelConfig};
+use hal9_core::{NeuronSignal, ServerConfig};
+use hal9_server::api::create_api_router;
+use hal9_server::chain_tracker::{ChainRecord, ChainStatus, ChainTracker};
+use hal9_server::concurrency::{ConcurrencyStats, DeadLetterQueue};
+use hal9_server::error::ServerError;
+use hal9_server::retention::{Archive, RetentionJanitor, SNAPSHOTS};
+use hal9_server::server::{HAL9Server, NeuronInfo};
+use hal9_server::time_travel::{self, reconstruct, StateSnapshot, TimeTravel};
+
+use common::call;
+
+/// Signals sent to and processed by neurons over a minute, `processed`
+/// `null` where the signal was never processed
+const TIMELINES: &str = include_str!("fixtures/time_travel_timelines.json");
+
+#[derive(Deserialize)]
+struct Timeline {
+    name: String,
+    start: DateTime<Utc>,
+    snapshot_interval_secs: u32,
+    duration_secs: u32,
+    neurons: Vec<FixtureNeuron>,
+    chains: Vec<FixtureChain>,
+}
+
+#[derive(Deserialize)]
+struct FixtureNeuron {
+    id: String,
+    layer: String,
+    max_concurrent: usize,
+}
+
+#[derive(Deserialize)]
+struct FixtureChain {
+    id: String,
+    signals: Vec<FixtureSignal>,
+}
+
+#[derive(Deserialize)]
+struct FixtureSignal {
+    neuron: String,
+    sent: f64,
+    processed: Option<f64>,
+}
+
+impl FixtureSignal {
+    fn in_flight(&self, t: f64) -> bool {
+        self.sent <= t && self.processed.is_none_or(|processed| t < processed)
+    }
+}
+
+impl Timeline {
+    fn at(&self, secs: f64) -> DateTime<Utc> {
+        self.start + chrono::Duration::milliseconds((secs * 1000.0).round() as i64)
+    }
+
+    fn signals(&self) -> impl Iterator<Item = &FixtureSignal> {
+        self.chains.iter().flat_map(|chain| &chain.signals)
+    }
+
+    /// Signals sent to `neuron` by `t` and not processed by then
+    fn depth(&self, neuron: &str, t: f64) -> usize {
+        self.signals().filter(|signal| signal.neuron == neuron && signal.in_flight(t)).count()
+    }
+
+    fn snapshot(&self, t: f64) -> StateSnapshot {
+        StateSnapshot {
+            taken_at: self.at(t),
+            neurons: self
+                .neurons
+                .iter()
+                .map(|n| neuron(&n.id, &n.layer, n.max_concurrent, self.depth(&n.id, t)))
+                .collect(),
+            costs: serde_json::from_value(json!({
+                "hourly_cost": 0.5, "hourly_tokens": 1200, "daily_cost": 2.0, "daily_tokens": 4800,
+                "total_cost": 2.0, "hourly_limit": 10.0, "daily_limit": 100.0, "period": null,
+            }))
+            .unwrap(),
+        }
+    }
+
+    /// Chain records as the tracker keeps them: a step per processed signal
+    fn records(&self) -> Vec<ChainRecord> {
+        self.chains
+            .iter()
+            .map(|chain| {
+                let layer_of = |id: &str| self.neurons.iter().find(|n| n.id == id).unwrap().layer.clone();
+                let mut processed: Vec<(usize, &FixtureSignal, f64)> = chain
+                    .signals
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(i, signal)| signal.processed.map(|processed| (i, signal, processed)))
+                    .collect();
+                processed.sort_by(|a, b| a.2.total_cmp(&b.2));
+                let steps: Vec<Value> = processed
+                    .iter()
+                    .map(|(i, signal, done)| {
+                        json!({
+                            "signal_id": format!("{}-{}", chain.id, i),
+                            "neuron_id": signal.neuron,
+                            "layer": layer_of(&signal.neuron),
+                            "direction": "Forward",
+                            "output": "RESULT: done",
+                            "duration_ms": (self.at(*done) - self.at(signal.sent)).num_milliseconds(),
+                            "prompt_tokens": 100,
+                            "completion_tokens": 20,
+                            "timestamp": self.at(*done),
+                        })
+                    })
+                    .collect();
+                let settled = processed.len() == chain.signals.len();
+                let status = if settled { "completed" } else { "running" };
+                let root = &chain.signals[0];
+                serde_json::from_value(json!({
+                    "chain_id": chain.id,
+                    "layer": layer_of(&root.neuron),
+                    "neuron_id": root.neuron,
+                    "input": "Plan it",
+                    "tags": {},
+                    "status": status,
+                    "created_at": self.at(root.sent),
+                    "completed_at": settled.then(|| self.at(processed.last().unwrap().2)),
+                    "steps": steps,
+                    "prompt_tokens": 100 * processed.len(),
+                    "completion_tokens": 20 * processed.len(),
+                }))
+                .unwrap()
+            })
+            .collect()
+    }
+}
+
+fn neuron(id: &str, layer: &str, max_concurrent: usize, depth: usize) -> NeuronInfo {
+    NeuronInfo {
+        id: id.to_string(),
+        layer: layer.to_string(),
+        state: "Running".to_string(),
+        is_healthy: true,
+        concurrency: ConcurrencyStats {
+            max_concurrent,
+            max_queued: max_concurrent * 4,
+            in_flight: depth.min(max_concurrent),
+            queued: depth.saturating_sub(max_concurrent),
+            ..Default::default()
+        },
+        circuit_breaker: "closed".to_string(),
+    }
+}
+
+#[test]
+fn test_queue_depths_match_fixture_timelines() {
+    let timelines: Vec<Timeline> = serde_json::from_str(TIMELINES).unwrap();
+    for timeline in timelines {
+        let interval = timeline.snapshot_interval_secs as f64;
+        let snapshots: Vec<StateSnapshot> = (0..=timeline.duration_secs / timeline.snapshot_interval_secs)
+            .map(|i| timeline.snapshot(i as f64 * interval))
+            .collect();
+        let records = timeline.records();
+        let settled = timeline.signals().all(|signal| signal.processed.is_some());
+
+        for half_secs in 0..=timeline.duration_secs * 2 {
+            let t = half_secs as f64 / 2.0;
+            let snapshot = &snapshots[(t / interval) as usize];
+            let view = reconstruct(timeline.at(t), snapshot, &records);
+            assert_eq!((view.as_of, view.snapshot_at), (timeline.at(t), snapshot.taken_at));
+
+            for neuron in &view.neurons {
+                let concurrency = &neuron.concurrency;
+                let depth = concurrency.in_flight + concurrency.queued;
+                let truth = timeline.depth(&neuron.id, t);
+                // Only signals never processed go unseen, and only those
+                // sent since the snapshot
+                let sent_since = timeline
+                    .signals()
+                    .filter(|signal| signal.neuron == neuron.id && signal.sent > t - interval && signal.sent <= t)
+                    .count();
+                let tolerance = if settled { 0 } else { sent_since };
+                assert!(
+                    depth.abs_diff(truth) <= tolerance,
+                    "{} at {}s: {} rebuilt {} deep, was {}",
+                    timeline.name, t, neuron.id, depth, truth
+                );
+                assert!(depth <= truth, "{} at {}s: {} overcounted", timeline.name, t, neuron.id);
+                assert_eq!(concurrency.in_flight, depth.min(concurrency.max_concurrent));
+            }
+
+            let running: Vec<&str> = view.chains.iter().map(|chain| chain.chain_id.as_str()).collect();
+            let expected: Vec<&str> = timeline
+                .chains
+                .iter()
+                .filter(|chain| chain.signals[0].sent <= t && chain.signals.iter().any(|signal| signal.in_flight(t)))
+                .map(|chain| chain.id.as_str())
+                .collect();
+            assert_eq!(running, expected, "{} at {}s", timeline.name, t);
+
+            for result in &view.chains {
+                let chain = timeline.chains.iter().find(|chain| chain.id == result.chain_id).unwrap();
+                let processed = chain.signals.iter().filter(|signal| signal.processed.is_some_and(|p| p <= t)).count();
+                assert_eq!(result.status, ChainStatus::Running);
+                assert_eq!(result.steps_completed, processed, "{} {} at {}s", timeline.name, chain.id, t);
+                assert_eq!(result.prompt_tokens, 100 * processed as u64);
+                if settled {
+                    let pending = chain.signals.iter().filter(|signal| signal.in_flight(t)).count();
+                    assert_eq!(result.pending_signals, pending, "{} {} at {}s", timeline.name, chain.id, t);
+                }
+            }
+        }
+    }
+}
+
+fn config(time_travel: bool) -> ServerConfig {
+    common::mock_config(json!({
+        "server_id": "time-travel-test",
+        "neurons": [
+            {"id": "planner", "layer": "L4", "forward_connections": ["coder"], "backward_connections": []},
+            {"id": "coder", "layer": "L2", "forward_connections": [], "backward_connections": ["planner"]},
+        ],
+        "claude": {
+            "mock_responses": {
+                "L4": [{"trigger": "default", "response": "FORWARD_TO: coder\nCONTENT: Build the cart", "delay_ms": 300}],
+                "L2": [{"trigger": "default", "response": "RESULT: Cart built", "delay_ms": 300}],
+            },
+        },
+        "memory": {"enabled": false},
+        "time_travel": {"enabled": time_travel, "snapshot_interval_secs": 3600},
+    }))
+}
+
+async fn state_at(app: &Router, at: DateTime<Utc>) -> (StatusCode, Value) {
+    let at = at.to_rfc3339_opts(chrono::SecondsFormat::Micros, true);
+    call(app, "GET", &format!("/api/v1/debug/at?timestamp={}", urlencoding::encode(&at)), None).await
+}
+
+fn depth(view: &Value, neuron: &str) -> u64 {
+    let neuron = view["neurons"].as_array().unwrap().iter().find(|n| n["id"] == neuron).unwrap();
+    neuron["concurrency"]["in_flight"].as_u64().unwrap() + neuron["concurrency"]["queued"].as_u64().unwrap()
+}
+
+#[tokio::test]
+async fn test_past_instants_over_the_api() {
+    let server = Arc::new(HAL9Server::new(config(true)));
+    server.start().await.unwrap();
+    let app = create_api_router(server.clone());
+    let before = Utc::now() - chrono::Duration::hours(1);
+    let (status, _) = state_at(&app, before).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    // The recorder takes its first snapshot as the server starts
+    tokio::time::timeout(Duration::from_secs(10), async {
+        while state_at(&app, Utc::now()).await.0 != StatusCode::OK {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("no snapshot recorded at startup");
+    let snapshot = server.record_state_snapshot().await;
+    assert!(snapshot.neurons.iter().all(|n| n.circuit_breaker == "closed"));
+    let chain_id = server
+        .submit_signal(NeuronSignal::forward("api-client", "planner", "API", "L4", "Build a cart".into()))
+        .await
+        .unwrap();
+    tokio::time::sleep(Duration::from_millis(150)).await;
+    let planning = Utc::now();
+    tokio::time::timeout(Duration::from_secs(10), async {
+        while server.get_chain_result(&chain_id).await.unwrap().status == ChainStatus::Running {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("chain did not finish");
+
+    // The planner was thinking, nothing had reached the coder yet
+    let (status, body) = state_at(&app, planning).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    let view = &body["data"];
+    assert_eq!(view["snapshot_at"], json!(snapshot.taken_at));
+    assert_eq!((depth(view, "planner"), depth(view, "coder")), (1, 0), "{}", view);
+    assert_eq!(view["chains"].as_array().unwrap().len(), 1);
+    assert_eq!(view["chains"][0]["chain_id"], chain_id.as_str());
+    assert_eq!(view["chains"][0]["status"], "running");
+    assert_eq!(view["chains"][0]["steps_completed"], 0);
+    assert_eq!(view["neurons"][0]["circuit_breaker"], "closed");
+    assert!(view["costs"]["hourly_limit"].is_number());
+
+    // Then the coder was working on what the planner forwarded
+    let planned = server.chain_tracker().get(&chain_id).unwrap().steps[0].timestamp;
+    let (_, body) = state_at(&app, planned + chrono::Duration::milliseconds(100)).await;
+    let view = &body["data"];
+    assert_eq!((depth(view, "planner"), depth(view, "coder")), (0, 1), "{}", view);
+    assert_eq!(view["chains"][0]["steps_completed"], 1);
+    assert_eq!(view["chains"][0]["layers"], json!(["L4"]));
+
+    let (_, body) = state_at(&app, Utc::now()).await;
+    assert!(body["data"]["chains"].as_array().unwrap().is_empty(), "{}", body);
+    assert_eq!((depth(&body["data"], "planner"), depth(&body["data"], "coder")), (0, 0));
+
+    let (status, _) = state_at(&app, Utc::now() + chrono::Duration::minutes(5)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, _) = call(&app, "GET", "/api/v1/debug/at?timestamp=yesterday", None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    server.shutdown().await.unwrap();
+
+    let disabled = Arc::new(HAL9Server::new(config(false)));
+    let (status, _) = state_at(&create_api_router(disabled), Utc::now()).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let broken = TimeTravelConfig { enabled: true, snapshot_interval_secs: 0 };
+    assert!(time_travel::validate(&broken).is_err());
+}
+
+#[tokio::test]
+async fn test_archived_snapshots_rebuild_past_instants() {
+    let dir = tempfile::tempdir().unwrap();
+    let tracker = Arc::new(ChainTracker::new());
+    let time_travel = Arc::new(TimeTravel::new(tracker.clone(), dir.path()));
+    let retention = RetentionConfig { archive_dir: dir.path().display().to_string(), ..Default::default() };
+    let janitor = RetentionJanitor::new(retention, tracker.clone(), Arc::new(DeadLetterQueue::new(16)));
+    janitor.set_time_travel(time_travel.clone());
+
+    let too_early = Utc::now();
+    let snapshot = |depth| StateSnapshot {
+        taken_at: Utc::now(),
+        neurons: vec![neuron("planner", "L4", 4, depth)],
+        costs: serde_json::from_value(json!({
+            "hourly_cost": 0.0, "hourly_tokens": 0, "daily_cost": 0.0, "daily_tokens": 0,
+            "total_cost": 0.0, "hourly_limit": 10.0, "daily_limit": 100.0, "period": null,
+        }))
+        .unwrap(),
+    };
+    time_travel.record(snapshot(0));
+    let mut root = NeuronSignal::forward("api-client", "planner", "API", "L4", "Plan it".into());
+    let chain_id = tracker.start(&mut root);
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    let planning = Utc::now();
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    tracker.record_step(&root, Ok("RESULT: planned"), 0);
+    time_travel.record(snapshot(0));
+
+    let live = time_travel.state_at(planning).await.unwrap();
+    assert_eq!(live.chains.len(), 1);
+    assert_eq!(live.chains[0].chain_id, chain_id);
+    assert_eq!(live.neurons[0].concurrency.in_flight, 1);
+
+    // Past payload retention, snapshots go with the chains
+    let report = janitor.run(Utc::now() + chrono::Duration::days(8)).await.unwrap();
+    assert_eq!((report.snapshots_archived, report.chains_archived), (2, 1));
+    assert!(time_travel.is_empty());
+    assert!(tracker.get(&chain_id).is_none());
+    assert_eq!(Archive::new(dir.path()).files(SNAPSHOTS).unwrap().len(), 1);
+
+    let archived = time_travel.state_at(planning).await.unwrap();
+    assert_eq!(serde_json::to_value(&archived).unwrap(), serde_json::to_value(&live).unwrap());
+    assert!(matches!(time_travel.state_at(too_early).await, Err(ServerError::NotFound(_))));
+}
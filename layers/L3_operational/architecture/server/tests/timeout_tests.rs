@@ -0,0 +1,203 @@
+//! Provider call timeouts: per-layer defaults, adaptation to a neuron's
+//! latency, its bounds, and the kill switch
+
+mod common;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::http::StatusCode;
+use serde_json::json;
+
+use hal9_core::config::{AdaptiveTimeoutConfig, TimeoutConfig};
+use hal9_core::metadata_schema::keys;
+use hal9_core::{Error, NeuronConfig, NeuronInterface, NeuronSignal};
+use hal9_server::api::create_api_router;
+use hal9_server::metrics::Metrics;
+use hal9_server::server::HAL9Server;
+use hal9_server::timeouts::{self, TimeoutPolicy, TimeoutSource, TIMEOUT_MS_KEY, TIMEOUT_SOURCE_KEY};
+use hal9_server::{ManagedNeuron, MockClaude};
+
+use common::call;
+
+fn config(enabled: bool) -> TimeoutConfig {
+    TimeoutConfig {
+        default_ms: 30_000,
+        layers_ms: HashMap::from([("L4".to_string(), 60_000)]),
+        adaptive: AdaptiveTimeoutConfig {
+            enabled,
+            percentile: 0.99,
+            factor: 3.0,
+            min_ms: 50,
+            max_ms: 10_000,
+            window: 10,
+            min_samples: 5,
+        },
+    }
+}
+
+fn record(policy: &TimeoutPolicy, neuron: &str, layer: &str, latency_ms: u64, times: usize) {
+    for _ in 0..times {
+        policy.record(neuron, layer, Duration::from_millis(latency_ms));
+    }
+}
+
+#[test]
+fn test_adaptive_timeout_tracks_latency_shift() {
+    let policy = TimeoutPolicy::new(config(true));
+
+    // Until a neuron has made enough calls, its layer's timeout applies
+    record(&policy, "planner", "L4", 100, 4);
+    let decision = policy.decide("planner", "L4");
+    assert_eq!((decision.timeout_ms, decision.source), (60_000, TimeoutSource::Layer));
+    assert_eq!(policy.decide("coder", "L2").source, TimeoutSource::Default);
+
+    record(&policy, "planner", "L4", 100, 1);
+    let decision = policy.decide("planner", "L4");
+    assert_eq!((decision.timeout_ms, decision.source), (300, TimeoutSource::Adaptive));
+    assert_eq!(decision.percentile_ms, Some(100));
+
+    // A slowdown pulls the timeout up once it fills the percentile...
+    record(&policy, "planner", "L4", 1_000, 1);
+    assert_eq!(policy.decide("planner", "L4").timeout_ms, 3_000);
+
+    // ...and the window forgets the old latency
+    record(&policy, "planner", "L4", 400, 10);
+    assert_eq!(policy.decide("planner", "L4").timeout_ms, 1_200);
+    assert_eq!(policy.status().neurons["planner"].samples, 10);
+}
+
+#[test]
+fn test_adaptive_timeout_bounds() {
+    let policy = TimeoutPolicy::new(config(true));
+    record(&policy, "reflex", "L1", 1, 5);
+    record(&policy, "strategist", "L5", 9_000, 5);
+
+    let fast = policy.decide("reflex", "L1");
+    assert_eq!((fast.timeout_ms, fast.percentile_ms), (50, Some(1)));
+    let slow = policy.decide("strategist", "L5");
+    assert_eq!((slow.timeout_ms, slow.percentile_ms), (10_000, Some(9_000)));
+
+    let invalid = |update: fn(&mut TimeoutConfig)| {
+        let mut config = config(true);
+        update(&mut config);
+        timeouts::validate(&config).unwrap_err().to_string()
+    };
+    assert!(timeouts::validate(&config(true)).is_ok());
+    assert!(invalid(|c| c.adaptive.min_ms = 20_000).contains("adaptive.min_ms"));
+    assert!(invalid(|c| c.adaptive.factor = 0.5).contains("adaptive.factor"));
+    assert!(invalid(|c| c.adaptive.percentile = 1.5).contains("adaptive.percentile"));
+    assert!(invalid(|c| { c.layers_ms.insert("L10".to_string(), 1_000); }).contains("unknown layer L10"));
+    assert!(invalid(|c| c.default_ms = 0).contains("positive"));
+}
+
+#[test]
+fn test_kill_switch_reverts_to_static_timeouts() {
+    let policy = TimeoutPolicy::new(config(true));
+    record(&policy, "planner", "L4", 100, 5);
+    assert_eq!(policy.decide("planner", "L4").source, TimeoutSource::Adaptive);
+
+    policy.set_adaptive(false);
+    let decision = policy.decide("planner", "L4");
+    assert_eq!((decision.timeout_ms, decision.source), (60_000, TimeoutSource::Layer));
+    assert_eq!(policy.status().neurons["planner"].next, decision);
+
+    // Latency recorded meanwhile is there when it is turned back on
+    record(&policy, "planner", "L4", 200, 5);
+    policy.set_adaptive(true);
+    assert_eq!(policy.decide("planner", "L4").timeout_ms, 600);
+}
+
+fn managed_neuron(policy: &Arc<TimeoutPolicy>, claude: MockClaude) -> ManagedNeuron {
+    let config = NeuronConfig {
+        id: "neuron-l2".to_string(),
+        layer: "L2".to_string(),
+        claude_command: "claude".to_string(),
+        system_prompt: None,
+        forward_connections: vec!["neuron-l1".to_string()],
+        backward_connections: vec![],
+        settings: HashMap::new(),
+    };
+    let mut neuron = ManagedNeuron::new(config, Box::new(claude)).unwrap();
+    neuron.set_metrics(Arc::new(Metrics::new()));
+    neuron.set_timeouts(policy.clone());
+    neuron
+}
+
+fn signal(content: &str) -> NeuronSignal {
+    NeuronSignal::forward("neuron-l3", "neuron-l2", "L3", "L2", content.into())
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_neuron_call_errors_name_their_timeout() {
+    let policy = Arc::new(TimeoutPolicy::new(config(true)));
+    record(&policy, "neuron-l2", "L2", 50, 5);
+    let mut slow = MockClaude::new("L2", &Default::default());
+    slow.set_delay(500);
+    let neuron = managed_neuron(&policy, slow);
+
+    let first = signal("first");
+    let err = neuron.process_signal(&first).await.unwrap_err();
+    assert!(matches!(err, Error::AdaptiveTimeout { timeout_ms: 150, .. }), "{}", err);
+    let decision = neuron.take_timeout_decision(&first.signal_id).unwrap();
+    assert_eq!(decision.source, TimeoutSource::Adaptive);
+
+    // A deadline sooner than the timeout is the signal's, not the neuron's
+    let mut due = signal("second");
+    let deadline = (chrono::Utc::now() + chrono::Duration::milliseconds(100)).to_rfc3339();
+    due.metadata.insert(keys::routing::DEADLINE.to_string(), deadline.clone());
+    let err = neuron.process_signal(&due).await.unwrap_err();
+    match err {
+        Error::DeadlineExceeded { neuron, deadline: expired } => {
+            assert_eq!((neuron.as_str(), expired), ("neuron-l2", deadline));
+        }
+        err => panic!("expected deadline expiry, got {}", err),
+    }
+    // Only the adaptive expiry counts as latency
+    assert_eq!(policy.status().neurons["neuron-l2"].samples, 6);
+
+    // With adaptive timeouts off the call gets the default again
+    policy.set_adaptive(false);
+    let neuron = managed_neuron(&policy, MockClaude::scripted("L2", vec!["FORWARD_TO: neuron-l1\nCONTENT: done".to_string()]));
+    let third = signal("third");
+    let response = neuron.process_signal(&third).await.unwrap();
+    let children = neuron.parse_response(&response, &third);
+    assert_eq!(children.len(), 1);
+    for child in &children {
+        assert_eq!(child.metadata[TIMEOUT_MS_KEY], "30000");
+        assert_eq!(child.metadata[TIMEOUT_SOURCE_KEY], "default");
+    }
+}
+
+#[tokio::test]
+async fn test_kill_switch_through_api() {
+    let config = common::mock_config(json!({
+        "server_id": "timeout-test",
+        "neurons": [
+            {"id": "planner", "layer": "L4", "forward_connections": [], "backward_connections": []},
+        ],
+        "claude": {
+            "mock_responses": {"L4": [{"trigger": "default", "response": "RESULT: Planned", "delay_ms": 0}]},
+        },
+        "memory": {"enabled": false},
+        "timeouts": {"layers_ms": {"L4": 45000}, "adaptive": {"enabled": true}},
+    }));
+    let server = Arc::new(HAL9Server::new(config));
+    server.start().await.unwrap();
+    let app = create_api_router(server.clone());
+
+    let (status, body) = call(&app, "GET", "/api/v1/admin/timeouts", None).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["data"]["adaptive"], true);
+    assert_eq!(body["data"]["layers_ms"]["L4"], 45000);
+
+    // The switch still works while the server is read-only
+    server.enable_read_only(Some("upgrade".to_string()), None, None);
+    let (status, body) = call(&app, "PUT", "/api/v1/admin/timeouts/adaptive", Some(json!({"enabled": false}))).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["data"]["adaptive"], false);
+    assert!(!server.timeout_status().adaptive);
+
+    server.shutdown().await.unwrap();
+}
@@ -0,0 +1,250 @@
+//! Tool sandbox: each per-chain limit refuses the call over it, parallel
+//! branches share one ledger, and the ledger shows on the chain's report
+
+mod common;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use serde_json::{json, Value};
+
+use hal9_core::config::{ToolLimits, ToolSandboxConfig};
+use hal9_core::mcp::{Tool, ToolDefinition, ToolRegistry};
+use hal9_core::{Error, NeuronSignal, Result};
+use hal9_server::chain_tracker::{ChainStatus, ChainTracker};
+use hal9_server::server::HAL9Server;
+use hal9_server::tool_sandbox::{ToolLimit, ToolSandbox, TEMPLATE_NAME_KEY};
+
+/// Answers with its parameters, after `sleep_ms` if given
+struct EchoTool;
+
+#[async_trait]
+impl Tool for EchoTool {
+    fn name(&self) -> &str {
+        "echo"
+    }
+
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: "echo".to_string(),
+            description: "Echo the parameters".to_string(),
+            input_schema: json!({"type": "object"}),
+        }
+    }
+
+    async fn execute(&self, params: Value) -> Result<Value> {
+        if let Some(ms) = params["sleep_ms"].as_u64() {
+            tokio::time::sleep(Duration::from_millis(ms)).await;
+        }
+        Ok(params)
+    }
+}
+
+fn registry() -> ToolRegistry {
+    let mut registry = ToolRegistry::new();
+    registry.register(Box::new(EchoTool));
+    registry
+}
+
+/// A sandbox with `limits` as its defaults and a running chain in it
+fn chain_sandbox(limits: ToolLimits) -> (Arc<ChainTracker>, ToolSandbox, NeuronSignal) {
+    let tracker = Arc::new(ChainTracker::new());
+    let sandbox = ToolSandbox::new(ToolSandboxConfig { default: limits, ..Default::default() }, tracker.clone());
+    let mut root = NeuronSignal::forward("api", "coder", "API", "L2", "build it".into());
+    let chain_id = tracker.start(&mut root);
+    sandbox.start(&chain_id, &root, None);
+    (tracker, sandbox, root)
+}
+
+/// A signal of the same chain as `root`, as a branch would carry
+fn branch(root: &NeuronSignal, neuron: &str) -> NeuronSignal {
+    let mut signal = NeuronSignal::forward("coder", neuron, "L2", "L2", "part".into());
+    signal.metadata = root.metadata.clone();
+    signal
+}
+
+fn refused_for(result: Result<Value>) -> String {
+    match result {
+        Err(e @ Error::ToolLimitExceeded { .. }) => {
+            assert!(!e.is_recoverable());
+            assert_eq!(e.class(), "tool_limit_exceeded");
+            match e {
+                Error::ToolLimitExceeded { limit, .. } => limit,
+                _ => unreachable!(),
+            }
+        }
+        other => panic!("expected a tool limit refusal, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_limits_resolve_template_then_role_then_default() {
+    let mut config = ToolSandboxConfig {
+        default: ToolLimits { max_invocations: Some(10), max_domains: Some(3), ..Default::default() },
+        ..Default::default()
+    };
+    config.roles.insert("guest".to_string(), ToolLimits { max_invocations: Some(2), ..Default::default() });
+    config.templates.insert("research".to_string(), ToolLimits { max_domains: Some(8), ..Default::default() });
+    let sandbox = ToolSandbox::new(config, Arc::new(ChainTracker::new()));
+
+    let limits = sandbox.limits_for(Some("guest"), Some("research"));
+    assert_eq!((limits.max_invocations, limits.max_domains), (Some(2), Some(8)));
+    let limits = sandbox.limits_for(Some("admin"), None);
+    assert_eq!((limits.max_invocations, limits.max_domains), (Some(10), Some(3)));
+
+    // A chain rendered from a template takes its limits from the root
+    let tracker = Arc::new(ChainTracker::new());
+    let mut config = ToolSandboxConfig::default();
+    config.templates.insert("research".to_string(), ToolLimits { max_invocations: Some(1), ..Default::default() });
+    let sandbox = ToolSandbox::new(config, tracker.clone());
+    let mut root = NeuronSignal::forward("api", "coder", "API", "L2", "build it".into());
+    root.metadata.insert(TEMPLATE_NAME_KEY.to_string(), "research".to_string());
+    let chain_id = tracker.start(&mut root);
+    sandbox.start(&chain_id, &root, Some("guest"));
+    assert_eq!(sandbox.ledger(&chain_id).unwrap().limits().max_invocations, Some(1));
+}
+
+#[tokio::test]
+async fn test_each_limit_refuses_the_call_over_it() {
+    let registry = registry();
+
+    // Invocations
+    let (tracker, sandbox, root) = chain_sandbox(ToolLimits { max_invocations: Some(2), ..Default::default() });
+    for _ in 0..2 {
+        sandbox.execute(&registry, &root, "echo", json!({})).await.unwrap();
+    }
+    let limit = refused_for(sandbox.execute(&registry, &root, "echo", json!({})).await);
+    assert_eq!(limit, "invocations");
+    let tools = tracker.aggregate(ChainTracker::chain_id_of(&root).unwrap()).unwrap().tools.unwrap();
+    assert_eq!((tools.invocations, tools.max_invocations), (2, Some(2)));
+    assert_eq!(tools.violations.len(), 1);
+    assert_eq!(tools.violations[0].limit, ToolLimit::Invocations);
+    assert_eq!(tools.violations[0].tool, "echo");
+
+    // Outbound bytes: a call that doesn't fit is refused, a smaller one isn't
+    let (_, sandbox, root) = chain_sandbox(ToolLimits { max_outbound_bytes: Some(40), ..Default::default() });
+    sandbox.execute(&registry, &root, "echo", json!({"text": "0123456789"})).await.unwrap();
+    let limit = refused_for(sandbox.execute(&registry, &root, "echo", json!({"text": "0123456789"})).await);
+    assert_eq!(limit, "outbound_bytes");
+    sandbox.execute(&registry, &root, "echo", json!({})).await.unwrap();
+    let usage = sandbox.ledger(ChainTracker::chain_id_of(&root).unwrap()).unwrap().usage();
+    assert_eq!(usage.outbound_bytes, r#"{"text":"0123456789"}"#.len() as u64 + 2);
+
+    // Domains: calls to domains already reached still go through
+    let (_, sandbox, root) = chain_sandbox(ToolLimits { max_domains: Some(2), ..Default::default() });
+    sandbox.execute(&registry, &root, "echo", json!({"url": "https://a.example.com/x"})).await.unwrap();
+    sandbox.execute(&registry, &root, "echo", json!({"urls": ["https://b.example.com", "not a url"]})).await.unwrap();
+    let limit = refused_for(sandbox.execute(&registry, &root, "echo", json!({"url": "http://c.example.com"})).await);
+    assert_eq!(limit, "domains");
+    sandbox.execute(&registry, &root, "echo", json!({"url": "https://a.example.com/y"})).await.unwrap();
+    let usage = sandbox.ledger(ChainTracker::chain_id_of(&root).unwrap()).unwrap().usage();
+    assert_eq!(usage.domains, ["a.example.com", "b.example.com"]);
+
+    // Tool time: a call running past what is left is cut short, and the
+    // chain's calls are refused from then on
+    let (_, sandbox, root) = chain_sandbox(ToolLimits { max_tool_time_ms: Some(150), ..Default::default() });
+    sandbox.execute(&registry, &root, "echo", json!({"sleep_ms": 100})).await.unwrap();
+    let limit = refused_for(sandbox.execute(&registry, &root, "echo", json!({"sleep_ms": 5000})).await);
+    assert_eq!(limit, "tool_time");
+    let limit = refused_for(sandbox.execute(&registry, &root, "echo", json!({})).await);
+    assert_eq!(limit, "tool_time");
+    let usage = sandbox.ledger(ChainTracker::chain_id_of(&root).unwrap()).unwrap().usage();
+    assert!(usage.tool_time_ms >= 150 && usage.tool_time_ms < 1000, "{}", usage.tool_time_ms);
+    assert_eq!(usage.invocations, 2);
+    assert_eq!(usage.violations.iter().map(|v| v.limit).collect::<Vec<_>>(), [ToolLimit::ToolTime; 2]);
+}
+
+#[tokio::test]
+async fn test_parallel_branches_share_the_ledger() {
+    let registry = Arc::new(registry());
+    let limits = ToolLimits { max_invocations: Some(10), max_outbound_bytes: Some(10_000), ..Default::default() };
+    let (tracker, sandbox, root) = chain_sandbox(limits);
+    let sandbox = Arc::new(sandbox);
+
+    let calls: Vec<_> = (0..50)
+        .map(|i| {
+            let (registry, sandbox) = (registry.clone(), sandbox.clone());
+            let signal = branch(&root, &format!("coder-{}", i % 5));
+            tokio::spawn(async move {
+                sandbox.execute(&registry, &signal, "echo", json!({"sleep_ms": 5})).await
+            })
+        })
+        .collect();
+    let mut admitted = 0;
+    for call in calls {
+        match call.await.unwrap() {
+            Ok(_) => admitted += 1,
+            result => assert_eq!(refused_for(result), "invocations"),
+        }
+    }
+
+    assert_eq!(admitted, 10);
+    let chain_id = ChainTracker::chain_id_of(&root).unwrap();
+    let tools = tracker.aggregate(chain_id).unwrap().tools.unwrap();
+    assert_eq!(tools.invocations, 10);
+    assert_eq!(tools.outbound_bytes, 10 * r#"{"sleep_ms":5}"#.len() as u64);
+    assert_eq!(tools.violations.len(), 40);
+
+    // A signal of no chain is not limited
+    let loose = NeuronSignal::forward("api", "coder", "API", "L2", "build it".into());
+    sandbox.execute(&registry, &loose, "echo", json!({})).await.unwrap();
+    assert_eq!(sandbox.ledger(chain_id).unwrap().usage().invocations, 10);
+}
+
+#[tokio::test]
+async fn test_chain_report_shows_the_ledger() {
+    let config = common::mock_config(json!({
+        "server_id": "tool-sandbox-test",
+        "neurons": [{"id": "coder", "layer": "L2", "forward_connections": [], "backward_connections": []}],
+        "claude": {
+            "mock_responses": {
+                "L2": [{"trigger": "default", "response": "TOOL: shell_execute {\"command\": \"echo\", \"args\": [\"hi\"]}", "delay_ms": 0}],
+            },
+        },
+        "tool_sandbox": {"default": {"max_invocations": 2}},
+    }));
+    let server = Arc::new(HAL9Server::new(config));
+    server.start().await.unwrap();
+
+    let signal = NeuronSignal::forward("api", "coder", "API", "L2", "build it".into());
+    let chain_id = server.submit_signal(signal).await.unwrap();
+    let result = tokio::time::timeout(Duration::from_secs(10), async {
+        loop {
+            let result = server.get_chain_result(&chain_id).await.unwrap();
+            if result.status != ChainStatus::Running {
+                return result;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+    })
+    .await
+    .expect("chain did not finish");
+
+    // The response kept asking for the tool; the third call was refused
+    let tools = result.tools.unwrap();
+    assert_eq!((tools.invocations, tools.max_invocations), (2, Some(2)));
+    assert_eq!(tools.violations.len(), 1);
+    assert_eq!(tools.violations[0].tool, "shell_execute");
+    assert_eq!(tools.violations[0].neuron_id, "coder");
+    let output = result.final_output.unwrap();
+    assert!(output.contains("TOOL_ERROR: Chain") && output.contains("tool invocations limit"), "{}", output);
+
+    server.shutdown().await.unwrap();
+}
+
+#[test]
+fn test_zero_limits_are_refused() {
+    let mut config = ToolSandboxConfig::default();
+    assert!(hal9_server::tool_sandbox::validate(&config).is_ok());
+    config.roles.insert("guest".to_string(), ToolLimits { max_invocations: Some(0), ..Default::default() });
+    let err = hal9_server::tool_sandbox::validate(&config).unwrap_err();
+    assert!(err.to_string().contains("tool_sandbox.roles.guest: max_invocations"), "{}", err);
+
+    // No domains at all is a limit of its own
+    let config = ToolSandboxConfig {
+        default: ToolLimits { max_domains: Some(0), ..Default::default() },
+        ..Default::default()
+    };
+    assert!(hal9_server::tool_sandbox::validate(&config).is_ok());
+}
@@ -0,0 +1,140 @@
+//! Topology patches: validation, apply and revert, token expiry
+
+mod common;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde_json::json;
+
+use hal9_core::NeuronConfig;
+use hal9_server::error::ServerError;
+use hal9_server::server::HAL9Server;
+use hal9_server::topology::{diff, validate_topology, PatchOp, TopologyEditor, RULES};
+
+fn neuron(id: &str, layer: &str, forwards: &[&str]) -> NeuronConfig {
+    serde_json::from_value(json!({
+        "id": id,
+        "layer": layer,
+        "system_prompt": format!("You are {}", id),
+        "forward_connections": forwards,
+        "backward_connections": [],
+    }))
+    .unwrap()
+}
+
+fn topology() -> Vec<NeuronConfig> {
+    vec![neuron("planner", "L4", &["designer"]), neuron("designer", "L3", &["coder"]), neuron("coder", "L2", &[])]
+}
+
+fn editor(ttl: Duration) -> TopologyEditor {
+    TopologyEditor::new(topology(), HashMap::new(), ttl)
+}
+
+fn ops(value: serde_json::Value) -> Vec<PatchOp> {
+    serde_json::from_value(value).unwrap()
+}
+
+#[test]
+fn test_invalid_patch_is_rejected_with_rule_references() {
+    let editor = editor(Duration::from_secs(60));
+    let patch = ops(json!([
+        {"op": "add_neuron", "neuron": {"id": "coder", "layer": "L0", "forward_connections": ["coder"], "backward_connections": []}},
+        {"op": "remove_neuron", "id": "designer"},
+        {"op": "set_connections", "id": "coder", "backward_connections": ["tester"]},
+    ]));
+
+    let err = editor.propose(patch).unwrap_err();
+    let ServerError::InvalidInput(message) = err else {
+        panic!("expected invalid input, got {:?}", err);
+    };
+    for rule in ["[unique-ids]", "[known-layer]", "[no-self-connections]", "[known-connections]"] {
+        assert!(message.contains(rule), "{} missing from {}", rule, message);
+    }
+    // The planner still forwards to the removed designer
+    assert!(message.contains("Neuron planner forwards to unknown neuron designer"), "{}", message);
+    // Nothing changed
+    assert!(diff(&topology(), &editor.neurons()).is_empty());
+
+    // Every reported rule is documented
+    let mut broken = topology();
+    broken[0].settings.insert("concurrency".to_string(), json!({"overflow": "shed", "fallback": "backup"}));
+    let violations = validate_topology(&broken, &HashMap::new());
+    assert_eq!(violations.len(), 1);
+    assert_eq!((violations[0].rule, violations[0].neuron.as_deref()), ("known-fallback", Some("planner")));
+    assert!(RULES.iter().any(|(rule, _)| *rule == violations[0].rule));
+
+    // Operations on missing neurons fail before validation
+    let err = editor.propose(ops(json!([{"op": "set_prompt", "id": "ghost", "system_prompt": null}]))).unwrap_err();
+    assert!(err.to_string().contains("ops[0]: No neuron with ID ghost"), "{}", err);
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_patch_token_expires() {
+    let editor = editor(Duration::from_secs(60));
+    let patch = ops(json!([{"op": "set_prompt", "id": "coder", "system_prompt": "Write tests first"}]));
+
+    let proposal = editor.propose(patch.clone()).unwrap();
+    tokio::time::advance(Duration::from_secs(61)).await;
+    let err = editor.take(&proposal.token).unwrap_err();
+    assert!(err.to_string().contains("expired"), "{}", err);
+    // Tokens are single use
+    assert!(matches!(editor.take(&proposal.token), Err(ServerError::NotFound(_))));
+
+    // A token is void once another patch lands first
+    let first = editor.propose(patch.clone()).unwrap();
+    let second = editor.propose(patch).unwrap();
+    tokio::time::advance(Duration::from_secs(30)).await;
+    let prepared = editor.take(&first.token).unwrap();
+    editor.commit(prepared, Some("ops")).unwrap();
+    let err = editor.take(&second.token).unwrap_err();
+    assert!(err.to_string().contains("changed since"), "{}", err);
+}
+
+#[tokio::test]
+async fn test_apply_and_revert_round_trip() {
+    let config = common::mock_config(json!({
+        "server_id": "topology-test",
+        "neurons": topology(),
+    }));
+    let server = Arc::new(HAL9Server::new(config));
+    server.start().await.unwrap();
+
+    let proposal = server
+        .propose_topology_patch(ops(json!([
+            {"op": "add_neuron", "neuron": {"id": "reviewer", "layer": "L3", "forward_connections": ["coder"], "backward_connections": []}},
+            {"op": "set_connections", "id": "planner", "forward_connections": ["designer", "reviewer"]},
+            {"op": "set_prompt", "id": "coder", "system_prompt": "Write tests first"},
+        ])))
+        .unwrap();
+    let paths: Vec<_> = proposal.diff.iter().map(|entry| entry.path.as_str()).collect();
+    assert_eq!(paths, vec!["neurons.planner.forward_connections", "neurons.coder.system_prompt", "neurons.reviewer"]);
+    assert_eq!(proposal.diff[0].after, Some(json!(["designer", "reviewer"])));
+    // Proposing changes nothing
+    assert!(server.registry().get("reviewer").is_none());
+
+    let applied = server.apply_topology_patch(&proposal.token, Some("ops")).await.unwrap();
+    assert_eq!((applied.version, applied.revertible), (1, 1));
+    assert_eq!(applied.diff, proposal.diff);
+    let planner = server.registry().get("planner").unwrap();
+    assert_eq!(planner.config.forward_connections, vec!["designer", "reviewer"]);
+    assert_eq!(server.registry().get("reviewer").unwrap().config.forward_connections, vec!["coder"]);
+    assert_eq!(
+        server.registry().get("coder").unwrap().config.system_prompt.as_deref(),
+        Some("Write tests first")
+    );
+    assert_eq!(server.topology().len(), 4);
+
+    let reverted = server.revert_topology_patch(Some("ops")).await.unwrap();
+    assert_eq!((reverted.version, reverted.revertible), (2, 0));
+    assert!(diff(&topology(), &server.topology()).is_empty());
+    assert!(server.registry().get("reviewer").is_none());
+    assert_eq!(server.registry().get("planner").unwrap().config.forward_connections, vec!["designer"]);
+    assert_eq!(
+        server.registry().get("coder").unwrap().config.system_prompt.as_deref(),
+        Some("You are coder")
+    );
+
+    assert!(matches!(server.revert_topology_patch(None).await, Err(ServerError::NotFound(_))));
+}
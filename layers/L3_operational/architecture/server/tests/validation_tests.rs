@@ -0,0 +1,161 @@
+//! Response validators and the neuron retry loop
+
+mod common;
+
+use std::collections::HashMap;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+use hal9_core::config::{ValidationConfig, ValidatorConfig};
+use hal9_core::{NeuronInterface, NeuronSignal};
+use hal9_server::metrics::Metrics;
+use hal9_server::validation::{
+    code_blocks, CodeSyntaxValidator, JsonSchemaValidator, MaxLengthValidator, ResponseValidator,
+    ValidationPipeline, VALIDATION_REASON_KEY, VALIDATION_RETRIES_KEY, VALIDATION_STATUS_KEY,
+};
+use hal9_server::{ManagedNeuron, MockClaude};
+use serde_json::{json, Value};
+
+use common::neuron_config;
+
+const BROKEN_RUST: &str = "FORWARD_TO: neuron-l1\nCONTENT: done\n```rust\nfn main() {\n    let x = (1 + 2;\n}\n```";
+const VALID_RUST: &str = "FORWARD_TO: neuron-l1\nCONTENT: done\n```rust\nfn main() {\n    let x = (1 + 2);\n}\n```";
+
+fn syntax_pipeline(max_retries: u32) -> ValidationPipeline {
+    ValidationPipeline::from_config(&ValidationConfig {
+        validators: vec![ValidatorConfig::CodeSyntax { languages: vec![] }],
+        max_retries,
+    })
+    .unwrap()
+}
+
+fn neuron(responses: &[&str], max_retries: u32, metrics: &Arc<Metrics>) -> ManagedNeuron {
+    let claude = MockClaude::scripted("L2", responses.iter().map(|r| r.to_string()).collect());
+    let mut neuron = ManagedNeuron::new(neuron_config("neuron-l2", "L2", &["neuron-l1"], Value::Null), Box::new(claude)).unwrap();
+    neuron.set_metrics(metrics.clone());
+    neuron.set_validation(syntax_pipeline(max_retries));
+    neuron
+}
+
+#[test]
+fn test_code_blocks_extraction() {
+    let blocks = code_blocks("intro\n```Rust\nfn a() {}\n```\ntext\n```\nplain\n```\n```python\nx = 1\n");
+    assert_eq!(blocks.len(), 3);
+    assert_eq!(blocks[0].language, "rust");
+    assert_eq!(blocks[0].code, "fn a() {}\n");
+    assert_eq!(blocks[0].line, 2);
+    assert_eq!(blocks[1].language, "");
+    // Unterminated blocks run to the end
+    assert_eq!(blocks[2].code, "x = 1\n");
+}
+
+#[test]
+fn test_code_syntax_validator() {
+    let validator = CodeSyntaxValidator::new(vec![]).unwrap();
+    assert!(validator.validate(VALID_RUST).is_ok());
+    assert!(validator.validate("```python\ndef f(x):\n    return x\n```").is_ok());
+    assert!(validator.validate("```javascript\nconst f = (x) => x * 2;\n```").is_ok());
+
+    let reason = validator.validate(BROKEN_RUST).unwrap_err();
+    assert!(reason.starts_with("rust code block has a syntax error at line 5"), "{}", reason);
+    assert!(validator.validate("```python\ndef f(x)\n    return x\n```").is_err());
+
+    // Unsupported and unselected languages are not checked
+    assert!(validator.validate("```yaml\n: : :\n```").is_ok());
+    let python_only = CodeSyntaxValidator::new(vec!["Python".to_string()]).unwrap();
+    assert!(python_only.validate(BROKEN_RUST).is_ok());
+    assert!(CodeSyntaxValidator::new(vec!["cobol".to_string()]).is_err());
+}
+
+#[test]
+fn test_json_schema_validator() {
+    let validator = JsonSchemaValidator::new(&json!({
+        "type": "object",
+        "required": ["status", "files"],
+        "properties": {
+            "status": {"enum": ["ok", "partial"]},
+            "files": {"type": "array", "items": {"type": "string"}}
+        }
+    }))
+    .unwrap();
+
+    assert!(validator.validate("```json\n{\"status\": \"ok\", \"files\": [\"a.rs\"]}\n```").is_ok());
+    assert!(validator.validate("FORWARD_TO: x\nCONTENT:\n{\"status\": \"partial\", \"files\": []}").is_ok());
+
+    let reason = validator.validate("{\"status\": \"done\", \"files\": [1]}").unwrap_err();
+    assert!(reason.starts_with("output does not match the JSON schema"), "{}", reason);
+    assert!(reason.contains("/status"), "{}", reason);
+
+    let reason = validator.validate("not json").unwrap_err();
+    assert!(reason.starts_with("output is not valid JSON"), "{}", reason);
+
+    assert!(JsonSchemaValidator::new(&json!({"type": "no-such-type"})).is_err());
+}
+
+#[test]
+fn test_max_length_validator() {
+    let validator = MaxLengthValidator::new(5);
+    assert!(validator.validate("héllo").is_ok());
+    assert_eq!(
+        validator.validate("héllo!").unwrap_err(),
+        "response is 6 characters, over the limit of 5"
+    );
+}
+
+#[test]
+fn test_neuron_settings_override_layer() {
+    let layers = HashMap::from([(
+        "L2".to_string(),
+        ValidationConfig {
+            validators: vec![ValidatorConfig::MaxLength { max_chars: 10 }],
+            max_retries: 1,
+        },
+    )]);
+
+    let pipeline = ValidationPipeline::for_neuron(&layers, &neuron_config("neuron-l2", "L2", &["neuron-l1"], Value::Null)).unwrap().unwrap();
+    assert_eq!(pipeline.max_retries(), 1);
+    assert!(pipeline.validate("short")[0].failure.is_none());
+
+    let settings = json!({"validation": {"max_retries": 3, "validators": [{"type": "max_length", "max_chars": 3}]}});
+    let pipeline = ValidationPipeline::for_neuron(&layers, &neuron_config("neuron-l2", "L2", &["neuron-l1"], settings)).unwrap().unwrap();
+    assert_eq!(pipeline.max_retries(), 3);
+    assert!(pipeline.validate("short")[0].failure.is_some());
+
+    assert!(ValidationPipeline::for_neuron(&HashMap::new(), &neuron_config("neuron-l2", "L2", &["neuron-l1"], Value::Null)).unwrap().is_none());
+}
+
+#[tokio::test]
+async fn test_retry_until_valid() {
+    let metrics = Arc::new(Metrics::new());
+    let neuron = neuron(&[BROKEN_RUST, VALID_RUST], 2, &metrics);
+    let signal = NeuronSignal::forward("client", "neuron-l2", "L3", "L2", "implement".to_string());
+
+    let response = neuron.process_signal(&signal).await.unwrap();
+    assert_eq!(response, VALID_RUST);
+
+    let signals = neuron.parse_response(&response, &signal);
+    assert_eq!(signals.len(), 1);
+    assert_eq!(signals[0].metadata[VALIDATION_STATUS_KEY], "passed");
+    assert_eq!(signals[0].metadata[VALIDATION_RETRIES_KEY], "1");
+    assert!(signals[0].metadata[VALIDATION_REASON_KEY].starts_with("code_syntax: rust code block"));
+
+    assert_eq!(metrics.validation_retries.load(Ordering::Relaxed), 1);
+    let results = metrics.snapshot().validation_results;
+    assert_eq!(results["code_syntax:failed"], 1);
+    assert_eq!(results["code_syntax:passed"], 1);
+}
+
+#[tokio::test]
+async fn test_error_after_retries_exhausted() {
+    let metrics = Arc::new(Metrics::new());
+    let neuron = neuron(&[BROKEN_RUST, BROKEN_RUST], 1, &metrics);
+    let signal = NeuronSignal::forward("client", "neuron-l2", "L3", "L2", "implement".to_string());
+
+    let err = neuron.process_signal(&signal).await.unwrap_err();
+    assert!(err.to_string().contains("failed validation after 1 retries"), "{}", err);
+    assert!(err.to_string().contains("line 5"), "{}", err);
+
+    assert_eq!(metrics.validation_retries.load(Ordering::Relaxed), 1);
+    assert_eq!(metrics.snapshot().validation_results["code_syntax:failed"], 2);
+    assert_eq!(metrics.signals_failed.load(Ordering::Relaxed), 1);
+}
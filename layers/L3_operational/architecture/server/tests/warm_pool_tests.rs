@@ -0,0 +1,167 @@
+//! Warm pool checkouts, on-demand fallback and idle recycling
+
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use hal9_core::config::WarmPoolConfig;
+use hal9_core::neuron::{NeuronHealth, NeuronState};
+use hal9_core::{Layer, NeuronInterface, NeuronSignal, Result};
+use hal9_server::metrics::Metrics;
+use hal9_server::warm_pool::{InstanceFactory, WarmPool};
+
+struct FakeNeuron {
+    id: String,
+    stopped: AtomicBool,
+}
+
+#[async_trait]
+impl NeuronInterface for FakeNeuron {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn layer(&self) -> Layer {
+        Layer::L2
+    }
+
+    async fn process_signal(&self, signal: &NeuronSignal) -> Result<String> {
+        Ok(signal.payload.activation.content.clone())
+    }
+
+    async fn health(&self) -> Result<NeuronHealth> {
+        let state = if self.stopped.load(Ordering::SeqCst) { NeuronState::Stopped } else { NeuronState::Running };
+        Ok(NeuronHealth { state, last_signal: None, signals_processed: 0, errors_count: 0, uptime_seconds: 0 })
+    }
+
+    async fn shutdown(&self) -> Result<()> {
+        self.stopped.store(true, Ordering::SeqCst);
+        Ok(())
+    }
+}
+
+/// Numbers its instances and keeps them, so tests can see which were shut down
+#[derive(Default)]
+struct FakeFactory {
+    created: parking_lot::Mutex<Vec<Arc<FakeNeuron>>>,
+}
+
+impl FakeFactory {
+    fn created(&self) -> usize {
+        self.created.lock().len()
+    }
+
+    fn stopped(&self, id: &str) -> bool {
+        self.created.lock().iter().any(|neuron| neuron.id == id && neuron.stopped.load(Ordering::SeqCst))
+    }
+}
+
+#[async_trait]
+impl InstanceFactory for FakeFactory {
+    async fn instantiate(&self) -> Result<Arc<dyn NeuronInterface>> {
+        let mut created = self.created.lock();
+        let neuron = Arc::new(FakeNeuron { id: format!("plugin-{}", created.len()), stopped: AtomicBool::new(false) });
+        created.push(neuron.clone());
+        Ok(neuron)
+    }
+}
+
+fn pool(size: usize, idle_ttl_secs: u64) -> (WarmPool, Arc<FakeFactory>, Arc<Metrics>) {
+    let pool = WarmPool::new(WarmPoolConfig {
+        sizes: HashMap::from([("plugin".to_string(), size)]),
+        idle_ttl_secs,
+        refresh_interval_ms: 1000,
+    });
+    let metrics = Arc::new(Metrics::new());
+    pool.set_metrics(metrics.clone());
+    let factory = Arc::new(FakeFactory::default());
+    pool.register("plugin", factory.clone());
+    (pool, factory, metrics)
+}
+
+/// Let spawned shutdowns run
+async fn settle() {
+    tokio::time::sleep(Duration::from_millis(1)).await;
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_exhausted_pool_instantiates_on_demand() {
+    let (pool, factory, metrics) = pool(2, 300);
+    pool.refresh().await;
+    assert_eq!(factory.created(), 2);
+
+    let first = pool.checkout("plugin").await.unwrap();
+    let second = pool.checkout("plugin").await.unwrap();
+    let third = pool.checkout("plugin").await.unwrap();
+    assert_eq!(factory.created(), 3);
+    let mut ids = vec![first.id().to_string(), second.id().to_string(), third.id().to_string()];
+    ids.sort();
+    assert_eq!(ids, vec!["plugin-0", "plugin-1", "plugin-2"]);
+
+    let stats = metrics.warm_pool_stats("plugin").unwrap();
+    assert_eq!((stats.hits, stats.misses, stats.idle), (2, 1, 0));
+    assert_eq!(stats.instantiation.count, 3);
+
+    // Two instances fill the pool again; the third is surplus
+    drop((first, second, third));
+    settle().await;
+    let status = &pool.status()[0];
+    assert_eq!((status.size, status.idle, status.in_use), (2, 2, 0));
+    assert_eq!((0..3).filter(|n| factory.stopped(&format!("plugin-{}", n))).count(), 1);
+
+    assert!(pool.checkout("unknown").await.is_err());
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_ttl_recycling_spares_checked_out_instances() {
+    let (pool, factory, metrics) = pool(1, 60);
+    pool.refresh().await;
+
+    let busy = pool.checkout("plugin").await.unwrap();
+    assert_eq!(busy.id(), "plugin-0");
+
+    // Long past the TTL, but the instance is still processing
+    tokio::time::advance(Duration::from_secs(120)).await;
+    pool.refresh().await;
+    assert!(!factory.stopped("plugin-0"));
+    assert_eq!(busy.health().await.unwrap().state, NeuronState::Running);
+    assert_eq!(metrics.warm_pool_stats("plugin").unwrap().recycled, 0);
+    // The pool refilled in the meantime
+    assert_eq!(factory.created(), 2);
+
+    // Back from processing, it is surplus to the refilled pool
+    drop(busy);
+    settle().await;
+    assert!(factory.stopped("plugin-0"));
+
+    // The idle replacement is recycled once it outlives the TTL
+    tokio::time::advance(Duration::from_secs(59)).await;
+    pool.refresh().await;
+    assert!(!factory.stopped("plugin-1"));
+    tokio::time::advance(Duration::from_secs(2)).await;
+    pool.refresh().await;
+    assert!(factory.stopped("plugin-1"));
+    assert_eq!(metrics.warm_pool_stats("plugin").unwrap().recycled, 1);
+    assert_eq!(pool.checkout("plugin").await.unwrap().id(), "plugin-2");
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_resizing_at_runtime() {
+    let (pool, factory, _) = pool(3, 300);
+    pool.refresh().await;
+    assert_eq!(factory.created(), 3);
+
+    pool.set_config(&WarmPoolConfig {
+        sizes: HashMap::from([("plugin".to_string(), 1)]),
+        ..WarmPoolConfig::default()
+    });
+    pool.refresh().await;
+    assert_eq!(pool.status()[0].idle, 1);
+    assert_eq!((0..3).filter(|n| factory.stopped(&format!("plugin-{}", n))).count(), 2);
+
+    pool.set_config(&WarmPoolConfig::default());
+    pool.refresh().await;
+    assert_eq!((pool.status()[0].size, pool.status()[0].idle), (0, 0));
+}
@@ -0,0 +1,166 @@
+//! Webhook delivery tests against a local receiver
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::{body::Bytes, extract::State, http::HeaderMap, http::StatusCode, routing::post, Router};
+use hal9_core::sqlite::{SqlitePools, SqliteTuning};
+use hal9_core::{config::WebhookConfig, NeuronSignal};
+use hal9_server::{
+    chain_tracker::ChainTracker,
+    webhooks::{self, CreateWebhookRequest, DeliveryStatus, WebhookEvent, WebhookManager},
+};
+use parking_lot::Mutex;
+
+const SECRET: &str = "test-secret";
+
+/// Receiver that fails the first `failures` requests with 500
+struct Receiver {
+    failures: usize,
+    hits: AtomicUsize,
+    verified: AtomicUsize,
+    events: Mutex<Vec<String>>,
+}
+
+async fn receive(State(receiver): State<Arc<Receiver>>, headers: HeaderMap, body: Bytes) -> StatusCode {
+    let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok()).unwrap_or_default().to_string();
+    let timestamp: i64 = header(webhooks::TIMESTAMP_HEADER).parse().unwrap_or_default();
+
+    if webhooks::verify(SECRET, timestamp, &body, &header(webhooks::SIGNATURE_HEADER)) {
+        receiver.verified.fetch_add(1, Ordering::SeqCst);
+    }
+    receiver.events.lock().push(header(webhooks::EVENT_HEADER));
+
+    if receiver.hits.fetch_add(1, Ordering::SeqCst) < receiver.failures {
+        StatusCode::INTERNAL_SERVER_ERROR
+    } else {
+        StatusCode::OK
+    }
+}
+
+async fn start_receiver(failures: usize) -> (String, Arc<Receiver>) {
+    let receiver = Arc::new(Receiver {
+        failures,
+        hits: AtomicUsize::new(0),
+        verified: AtomicUsize::new(0),
+        events: Mutex::new(Vec::new()),
+    });
+    let app = Router::new().route("/hook", post(receive)).with_state(receiver.clone());
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+    (format!("http://{}/hook", addr), receiver)
+}
+
+fn manager() -> Arc<WebhookManager> {
+    Arc::new(WebhookManager::new(WebhookConfig {
+        max_attempts: 3,
+        initial_backoff_ms: 10,
+        max_backoff_ms: 50,
+        ..Default::default()
+    }))
+}
+
+async fn register(manager: &WebhookManager, url: &str) -> String {
+    manager.register("org-1", CreateWebhookRequest {
+        url: url.to_string(),
+        secret: Some(SECRET.to_string()),
+        events: vec![WebhookEvent::ChainCompleted],
+        description: None,
+    }).await.unwrap().webhook.id
+}
+
+fn completed_chain() -> hal9_server::chain_tracker::ChainResult {
+    let tracker = ChainTracker::new();
+    let mut signal = NeuronSignal::forward("api", "n", "API", "L2", "write code".into());
+    let chain_id = tracker.start(&mut signal);
+    tracker.record_step(&signal, Ok("done"), 0);
+    tracker.aggregate(&chain_id).unwrap()
+}
+
+async fn wait_for_status(manager: &WebhookManager, id: &str, status: DeliveryStatus) -> webhooks::Delivery {
+    for _ in 0..100 {
+        if let Some(delivery) = manager.deliveries("org-1", id).unwrap().into_iter().next() {
+            if delivery.status == status {
+                return delivery;
+            }
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    panic!("delivery never reached {:?}", status);
+}
+
+#[tokio::test]
+async fn test_delivery_is_signed_and_retried_after_500s() {
+    let (url, receiver) = start_receiver(2).await;
+    let manager = manager();
+    let id = register(&manager, &url).await;
+
+    manager.emit_chain("org-1", &completed_chain());
+
+    let delivery = wait_for_status(&manager, &id, DeliveryStatus::Delivered).await;
+    assert_eq!(delivery.attempts.len(), 3);
+    assert_eq!(delivery.attempts[0].status_code, Some(500));
+    assert_eq!(delivery.attempts[2].status_code, Some(200));
+    assert_eq!(receiver.hits.load(Ordering::SeqCst), 3);
+    assert_eq!(receiver.verified.load(Ordering::SeqCst), 3);
+    assert!(receiver.events.lock().iter().all(|e| e == "chain.completed"));
+}
+
+#[tokio::test]
+async fn test_delivery_dead_lettered_after_max_attempts() {
+    let (url, receiver) = start_receiver(usize::MAX).await;
+    let manager = manager();
+    let id = register(&manager, &url).await;
+
+    manager.emit_chain("org-1", &completed_chain());
+
+    let delivery = wait_for_status(&manager, &id, DeliveryStatus::DeadLettered).await;
+    assert_eq!(delivery.attempts.len(), 3);
+    assert_eq!(receiver.hits.load(Ordering::SeqCst), 3);
+}
+
+#[tokio::test]
+async fn test_events_only_reach_matching_subscriptions() {
+    let (url, receiver) = start_receiver(0).await;
+    let manager = manager();
+    let id = register(&manager, &url).await;
+
+    // Other organization's chain and an unsubscribed event are not delivered
+    manager.emit_chain("org-2", &completed_chain());
+    manager.emit(WebhookEvent::BudgetThreshold, None, serde_json::json!({}));
+
+    let delivery = manager.send_test("org-1", &id).await.unwrap();
+    assert_eq!(delivery.status, DeliveryStatus::Delivered);
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert_eq!(*receiver.events.lock(), vec![webhooks::TEST_EVENT.to_string()]);
+}
+
+#[tokio::test]
+async fn test_subscriptions_survive_a_restart() {
+    let dir = tempfile::tempdir().unwrap();
+    let database = format!("sqlite:{}?mode=rwc", dir.path().join("auth.db").display());
+    let pools = SqlitePools::connect(&database, SqliteTuning::default()).await.unwrap();
+    let (url, receiver) = start_receiver(0).await;
+
+    let before = manager();
+    before.attach_store(pools.clone()).await.unwrap();
+    let id = register(&before, &url).await;
+    let removed = register(&before, "https://example.com/removed").await;
+    before.delete("org-1", &removed).await.unwrap();
+    drop(before);
+
+    let after = manager();
+    after.attach_store(pools).await.unwrap();
+    let webhooks = after.list("org-1");
+    assert_eq!(webhooks.iter().map(|w| w.id.as_str()).collect::<Vec<_>>(), vec![id.as_str()]);
+    assert!(!format!("{:?}", webhooks[0]).contains(SECRET));
+
+    // The secret came back too: deliveries are still signed with it
+    after.emit_chain("org-1", &completed_chain());
+    wait_for_status(&after, &id, DeliveryStatus::Delivered).await;
+    assert_eq!(receiver.verified.load(Ordering::SeqCst), 1);
+}
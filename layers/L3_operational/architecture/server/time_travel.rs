@@ -0,0 +1,232 @@
+//! Debug views of the server at a past instant
+//!
+//! With `time_travel.enabled`, a task records a [`StateSnapshot`] every
+//! `snapshot_interval_secs`: each neuron's listing, with its queue depth and
+//! circuit breaker state, and the cost stats with the current budget
+//! period. [`reconstruct`] rebuilds the server at an instant from the last
+//! snapshot before it and the chains running around it:
+//!
+//! - chains in flight at the instant, cut down to the steps processed by
+//!   then, see [`ChainRecord::as_of`]
+//! - queue depths, the snapshot's depths moved by the signals each neuron
+//!   was sent and processed since, as the chains' steps record them
+//! - circuit breakers, neuron states and budgets, as of the snapshot
+//!
+//! Signals not processed yet when the view is rebuilt are unknown to it, so
+//! a depth can be short by what the neuron was sent since the snapshot.
+//! Snapshots follow payload retention: the janitor archives those older
+//! than `retention.payload_days` together with the chains, and both are
+//! read back from the archive for instants that far back.
+
+use std::collections::{HashMap, VecDeque};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+
+use hal9_core::config::TimeTravelConfig;
+use hal9_core::{Error, Result};
+
+use crate::{
+    chain_tracker::{summarize, ChainRecord, ChainResult, ChainTracker},
+    cost_tracker::{CostStats, CostTracker},
+    error::{ServerError, ServerResult},
+    neuron::NeuronRegistry,
+    retention::{Archive, CHAINS, SNAPSHOTS},
+    server::NeuronInfo,
+};
+
+/// Check that snapshots are taken at all
+pub fn validate(config: &TimeTravelConfig) -> Result<()> {
+    if config.enabled && config.snapshot_interval_secs == 0 {
+        return Err(Error::Config(
+            "Invalid time_travel config: snapshot_interval_secs must be at least 1".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Neurons and budgets at one instant
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StateSnapshot {
+    pub taken_at: DateTime<Utc>,
+    pub neurons: Vec<NeuronInfo>,
+    pub costs: CostStats,
+}
+
+impl StateSnapshot {
+    /// The registry's neurons and the cost stats as they stand now
+    pub async fn take(registry: &NeuronRegistry, cost_tracker: &CostTracker) -> Self {
+        Self {
+            taken_at: Utc::now(),
+            neurons: registry.list_all().await,
+            costs: cost_tracker.get_stats().await,
+        }
+    }
+}
+
+/// The server at `as_of`, in the shapes of the live endpoints
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PointInTime {
+    pub as_of: DateTime<Utc>,
+    /// Snapshot the view was rebuilt from
+    pub snapshot_at: DateTime<Utc>,
+    /// As listed by `GET /api/v1/neurons`
+    pub neurons: Vec<NeuronInfo>,
+    /// Chains in flight at `as_of`, oldest first, as returned by
+    /// `GET /api/v1/chains/:id`
+    pub chains: Vec<ChainResult>,
+    /// As returned by `GET /api/v1/costs`
+    pub costs: CostStats,
+}
+
+/// Rebuild the server at `at` from the last snapshot before it and the
+/// chains running at some point in between
+pub fn reconstruct(at: DateTime<Utc>, snapshot: &StateSnapshot, chains: &[ChainRecord]) -> PointInTime {
+    let since = snapshot.taken_at;
+
+    // Signals sent to each neuron since the snapshot, less those processed
+    let mut moved: HashMap<&str, i64> = HashMap::new();
+    for step in chains.iter().flat_map(|record| &record.steps) {
+        let sent = step.sent_at();
+        let arrived = i64::from(sent > since && sent <= at);
+        let departed = i64::from(step.timestamp > since && step.timestamp <= at);
+        *moved.entry(step.neuron_id.as_str()).or_default() += arrived - departed;
+    }
+
+    let neurons = snapshot
+        .neurons
+        .iter()
+        .map(|neuron| {
+            let mut neuron = neuron.clone();
+            let concurrency = &mut neuron.concurrency;
+            let change = moved.get(neuron.id.as_str()).copied().unwrap_or(0);
+            let depth = ((concurrency.in_flight + concurrency.queued) as i64 + change).max(0) as usize;
+            concurrency.in_flight = depth.min(concurrency.max_concurrent);
+            concurrency.queued = depth - concurrency.in_flight;
+            neuron
+        })
+        .collect();
+
+    let mut in_flight: Vec<&ChainRecord> = chains
+        .iter()
+        .filter(|record| record.created_at <= at && record.completed_at.is_none_or(|done| done > at))
+        .collect();
+    in_flight.sort_by(|a, b| (a.created_at, &a.chain_id).cmp(&(b.created_at, &b.chain_id)));
+
+    PointInTime {
+        as_of: at,
+        snapshot_at: since,
+        neurons,
+        chains: in_flight.into_iter().map(|record| summarize(&record.as_of(at))).collect(),
+        costs: snapshot.costs.clone(),
+    }
+}
+
+/// Snapshots taken since the last archival, and where older ones went
+pub struct TimeTravel {
+    /// Oldest first
+    snapshots: Mutex<VecDeque<StateSnapshot>>,
+    chain_tracker: Arc<ChainTracker>,
+    archive: Archive,
+}
+
+impl TimeTravel {
+    /// Snapshots archived to `archive_dir`, the janitor's archive
+    pub fn new(chain_tracker: Arc<ChainTracker>, archive_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            snapshots: Mutex::new(VecDeque::new()),
+            chain_tracker,
+            archive: Archive::new(archive_dir),
+        }
+    }
+
+    /// Keep a snapshot; snapshots are expected in the order they were taken
+    pub fn record(&self, snapshot: StateSnapshot) {
+        let mut snapshots = self.snapshots.lock();
+        let position = snapshots.partition_point(|kept| kept.taken_at <= snapshot.taken_at);
+        snapshots.insert(position, snapshot);
+    }
+
+    /// Snapshots kept in memory
+    pub fn len(&self) -> usize {
+        self.snapshots.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.snapshots.lock().is_empty()
+    }
+
+    /// Snapshots in memory taken before `cutoff`, to archive
+    pub fn snapshots_before(&self, cutoff: DateTime<Utc>) -> Vec<StateSnapshot> {
+        self.snapshots.lock().iter().take_while(|snapshot| snapshot.taken_at < cutoff).cloned().collect()
+    }
+
+    /// Forget snapshots taken before `cutoff`, once archived
+    pub fn drop_before(&self, cutoff: DateTime<Utc>) {
+        let mut snapshots = self.snapshots.lock();
+        let expired = snapshots.partition_point(|snapshot| snapshot.taken_at < cutoff);
+        snapshots.drain(..expired);
+    }
+
+    /// The server at `at`. Past payload retention, both the snapshot and
+    /// the chains come from the archive.
+    pub async fn state_at(&self, at: DateTime<Utc>) -> ServerResult<PointInTime> {
+        if at > Utc::now() {
+            return Err(ServerError::InvalidInput(format!("{} is in the future", at.to_rfc3339())));
+        }
+
+        let kept = self.snapshots.lock().iter().rev().find(|snapshot| snapshot.taken_at <= at).cloned();
+        if let Some(snapshot) = kept {
+            let chains = self.chain_tracker.overlapping(snapshot.taken_at, at);
+            return Ok(reconstruct(at, &snapshot, &chains));
+        }
+
+        // A chain that ran after the snapshot was taken was archived, if at
+        // all, to a file written later
+        let archive = self.archive.clone();
+        let (snapshot, archived) = tokio::task::spawn_blocking(move || -> ServerResult<_> {
+            let Some(snapshot) = archived_snapshot(&archive, at)? else {
+                return Ok((None, Vec::new()));
+            };
+            let mut chains = Vec::new();
+            for path in archive.files_since(CHAINS, snapshot.taken_at)? {
+                let records: Vec<ChainRecord> = Archive::read(&path)?;
+                chains.extend(records.into_iter().filter(|record| {
+                    record.created_at <= at && record.completed_at.is_none_or(|done| done >= snapshot.taken_at)
+                }));
+            }
+            Ok((Some(snapshot), chains))
+        })
+        .await
+        .map_err(|e| ServerError::Internal(format!("Archive reader panicked: {}", e)))??;
+
+        let snapshot = snapshot
+            .ok_or_else(|| ServerError::NotFound(format!("No state snapshot at or before {}", at.to_rfc3339())))?;
+        // Chains a dead letter holds back, or finished since the last
+        // janitor run, are still tracked
+        let mut chains: HashMap<String, ChainRecord> =
+            archived.into_iter().map(|record| (record.chain_id.clone(), record)).collect();
+        for record in self.chain_tracker.overlapping(snapshot.taken_at, at) {
+            chains.insert(record.chain_id.clone(), record);
+        }
+        let chains: Vec<ChainRecord> = chains.into_values().collect();
+        Ok(reconstruct(at, &snapshot, &chains))
+    }
+}
+
+/// Last archived snapshot taken at or before `at`. Each file holds the
+/// snapshots one janitor run archived, so the newest file holding one
+/// taken by then holds the last.
+fn archived_snapshot(archive: &Archive, at: DateTime<Utc>) -> ServerResult<Option<StateSnapshot>> {
+    for path in archive.files(SNAPSHOTS)? {
+        let snapshots: Vec<StateSnapshot> = Archive::read(&path)?;
+        let last = snapshots.into_iter().filter(|snapshot| snapshot.taken_at <= at).max_by_key(|snapshot| snapshot.taken_at);
+        if last.is_some() {
+            return Ok(last);
+        }
+    }
+    Ok(None)
+}
@@ -0,0 +1,277 @@
+//! Per-layer and adaptive timeouts of provider calls
+//!
+//! Each provider call a neuron makes times out after its layer's entry in
+//! `timeouts.layers_ms`, or `timeouts.default_ms`. With adaptive timeouts on,
+//! a neuron that has made `adaptive.min_samples` calls instead gets a
+//! percentile of its recent call latencies times `adaptive.factor`, bounded
+//! by `adaptive.min_ms` and `adaptive.max_ms`. Calls that time out count as
+//! taking their timeout, so a neuron that slows down past its timeout pulls
+//! the timeout up with it instead of failing every call.
+//!
+//! The timeout chosen for a call is written to the signals its response
+//! produces under `timeout.*`, so each decision can be audited. Switching
+//! adaptive timeouts off takes effect on the next call; latencies keep
+//! being recorded, so switching them back on resumes where they left off.
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use dashmap::DashMap;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use hal9_core::{config::TimeoutConfig, metadata_schema::keys, Error, Layer, Result};
+
+/// Metadata key of a call's timeout
+pub const TIMEOUT_MS_KEY: &str = keys::timeout::MS;
+
+/// Metadata key of how a call's timeout was chosen
+pub const TIMEOUT_SOURCE_KEY: &str = keys::timeout::SOURCE;
+
+/// Metadata key of the percentile an adaptive timeout was scaled from
+pub const TIMEOUT_PERCENTILE_KEY: &str = keys::timeout::PERCENTILE_MS;
+
+/// How a call's timeout was chosen
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TimeoutSource {
+    /// `timeouts.default_ms`
+    Default,
+    /// The layer's entry in `timeouts.layers_ms`
+    Layer,
+    /// Learned from the neuron's latency
+    Adaptive,
+}
+
+impl TimeoutSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TimeoutSource::Default => "default",
+            TimeoutSource::Layer => "layer",
+            TimeoutSource::Adaptive => "adaptive",
+        }
+    }
+}
+
+/// Timeout of one provider call
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TimeoutDecision {
+    pub timeout_ms: u64,
+    pub source: TimeoutSource,
+    /// Latency percentile an adaptive timeout was scaled from
+    pub percentile_ms: Option<u64>,
+}
+
+impl TimeoutDecision {
+    pub fn duration(&self) -> Duration {
+        Duration::from_millis(self.timeout_ms)
+    }
+
+    /// Write the decision into signal metadata
+    pub fn apply_to(&self, metadata: &mut HashMap<String, String>) {
+        metadata.insert(TIMEOUT_MS_KEY.to_string(), self.timeout_ms.to_string());
+        metadata.insert(TIMEOUT_SOURCE_KEY.to_string(), self.source.as_str().to_string());
+        match self.percentile_ms {
+            Some(percentile) => {
+                metadata.insert(TIMEOUT_PERCENTILE_KEY.to_string(), percentile.to_string());
+            }
+            None => {
+                metadata.remove(TIMEOUT_PERCENTILE_KEY);
+            }
+        }
+    }
+}
+
+/// A neuron's recent latency and the timeout its next call gets
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NeuronTimeout {
+    pub layer: String,
+    /// Calls in the latency window
+    pub samples: usize,
+    pub percentile_ms: Option<u64>,
+    #[serde(flatten)]
+    pub next: TimeoutDecision,
+}
+
+/// Timeout settings and what each neuron's next call gets
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TimeoutStatus {
+    /// Whether adaptive timeouts are in use right now
+    pub adaptive: bool,
+    pub default_ms: u64,
+    pub layers_ms: BTreeMap<String, u64>,
+    pub percentile: f64,
+    pub factor: f64,
+    pub min_ms: u64,
+    pub max_ms: u64,
+    pub neurons: BTreeMap<String, NeuronTimeout>,
+}
+
+/// Latencies of a neuron's most recent calls, oldest first
+#[derive(Debug)]
+struct LatencyWindow {
+    layer: String,
+    samples: VecDeque<u64>,
+}
+
+impl LatencyWindow {
+    /// Nearest-rank `percentile` of the window
+    fn percentile(&self, percentile: f64) -> Option<u64> {
+        if self.samples.is_empty() {
+            return None;
+        }
+        let mut sorted: Vec<u64> = self.samples.iter().copied().collect();
+        sorted.sort_unstable();
+        let rank = (percentile * sorted.len() as f64).ceil() as usize;
+        Some(sorted[rank.clamp(1, sorted.len()) - 1])
+    }
+}
+
+/// Chooses the timeout of each provider call
+pub struct TimeoutPolicy {
+    config: TimeoutConfig,
+    /// Kill switch of adaptive timeouts
+    adaptive: AtomicBool,
+    latencies: DashMap<String, Mutex<LatencyWindow>>,
+}
+
+impl Default for TimeoutPolicy {
+    fn default() -> Self {
+        Self::new(TimeoutConfig::default())
+    }
+}
+
+impl TimeoutPolicy {
+    pub fn new(config: TimeoutConfig) -> Self {
+        Self {
+            adaptive: AtomicBool::new(config.adaptive.enabled),
+            config,
+            latencies: DashMap::new(),
+        }
+    }
+
+    pub fn is_adaptive(&self) -> bool {
+        self.adaptive.load(Ordering::Relaxed)
+    }
+
+    /// Turn adaptive timeouts on or off. Off, every call gets its layer's
+    /// static timeout from the next call on.
+    pub fn set_adaptive(&self, enabled: bool) {
+        if self.adaptive.swap(enabled, Ordering::Relaxed) != enabled {
+            warn!("Adaptive timeouts turned {}", if enabled { "on" } else { "off" });
+        }
+    }
+
+    /// Timeout of `layer` from configuration alone
+    pub fn static_timeout(&self, layer: &str) -> TimeoutDecision {
+        let source = if self.config.layers_ms.contains_key(layer) {
+            TimeoutSource::Layer
+        } else {
+            TimeoutSource::Default
+        };
+        TimeoutDecision {
+            timeout_ms: self.config.layer_ms(layer),
+            source,
+            percentile_ms: None,
+        }
+    }
+
+    /// Timeout of the next call `neuron_id` makes
+    pub fn decide(&self, neuron_id: &str, layer: &str) -> TimeoutDecision {
+        if !self.is_adaptive() {
+            return self.static_timeout(layer);
+        }
+        let adaptive = self.latencies.get(neuron_id).and_then(|window| self.adaptive_timeout(&window.lock()));
+        adaptive.unwrap_or_else(|| self.static_timeout(layer))
+    }
+
+    /// Adaptive timeout of a neuron, once it has made enough calls
+    fn adaptive_timeout(&self, window: &LatencyWindow) -> Option<TimeoutDecision> {
+        let adaptive = &self.config.adaptive;
+        if window.samples.len() < adaptive.min_samples.max(1) {
+            return None;
+        }
+        let percentile = window.percentile(adaptive.percentile)?;
+        let scaled = (percentile as f64 * adaptive.factor).ceil() as u64;
+        Some(TimeoutDecision {
+            timeout_ms: scaled.clamp(adaptive.min_ms, adaptive.max_ms),
+            source: TimeoutSource::Adaptive,
+            percentile_ms: Some(percentile),
+        })
+    }
+
+    /// Record how long a call of `neuron_id` took; a call that timed out is
+    /// recorded as taking its timeout
+    pub fn record(&self, neuron_id: &str, layer: &str, latency: Duration) {
+        let capacity = self.config.adaptive.window.max(1);
+        let entry = self.latencies.entry(neuron_id.to_string()).or_insert_with(|| {
+            Mutex::new(LatencyWindow {
+                layer: layer.to_string(),
+                samples: VecDeque::with_capacity(capacity),
+            })
+        });
+        let mut window = entry.lock();
+        if window.samples.len() >= capacity {
+            window.samples.pop_front();
+        }
+        window.samples.push_back(latency.as_millis() as u64);
+    }
+
+    pub fn status(&self) -> TimeoutStatus {
+        let adaptive = &self.config.adaptive;
+        let neurons = self.latencies.iter()
+            .map(|entry| {
+                let window = entry.value().lock();
+                let next = self.is_adaptive()
+                    .then(|| self.adaptive_timeout(&window))
+                    .flatten()
+                    .unwrap_or_else(|| self.static_timeout(&window.layer));
+                (entry.key().clone(), NeuronTimeout {
+                    layer: window.layer.clone(),
+                    samples: window.samples.len(),
+                    percentile_ms: window.percentile(adaptive.percentile),
+                    next,
+                })
+            })
+            .collect();
+        TimeoutStatus {
+            adaptive: self.is_adaptive(),
+            default_ms: self.config.default_ms,
+            layers_ms: self.config.layers_ms.iter().map(|(layer, ms)| (layer.clone(), *ms)).collect(),
+            percentile: adaptive.percentile,
+            factor: adaptive.factor,
+            min_ms: adaptive.min_ms,
+            max_ms: adaptive.max_ms,
+            neurons,
+        }
+    }
+}
+
+/// Check that timeouts are positive, name known layers, and the adaptive
+/// bounds make sense
+pub fn validate(config: &TimeoutConfig) -> Result<()> {
+    let adaptive = &config.adaptive;
+    let unknown = config.layers_ms.keys().find(|layer| Layer::from_str(layer).is_none());
+    let invalid = if let Some(layer) = unknown {
+        Some(format!("unknown layer {}", layer))
+    } else if config.default_ms == 0 || config.layers_ms.values().any(|ms| *ms == 0) {
+        Some("timeouts must be positive".to_string())
+    } else if adaptive.percentile.is_nan() || adaptive.percentile <= 0.0 || adaptive.percentile > 1.0 {
+        Some(format!("adaptive.percentile must be in (0, 1], got {}", adaptive.percentile))
+    } else if adaptive.factor.is_nan() || adaptive.factor < 1.0 {
+        Some(format!("adaptive.factor must be at least 1, got {}", adaptive.factor))
+    } else if adaptive.min_ms == 0 || adaptive.min_ms > adaptive.max_ms {
+        Some(format!(
+            "adaptive.min_ms ({}) must be positive and at most adaptive.max_ms ({})",
+            adaptive.min_ms, adaptive.max_ms
+        ))
+    } else {
+        None
+    };
+    match invalid {
+        Some(reason) => Err(Error::Config(format!("Invalid timeouts: {}", reason))),
+        None => Ok(()),
+    }
+}
@@ -0,0 +1,386 @@
+//! Per-chain limits on tool calls
+//!
+//! Each chain gets a ledger of its tool calls when it starts, with limits
+//! resolved from `tool_sandbox`: those of the template the chain was
+//! rendered from, else of the submitting role, else the defaults, limit by
+//! limit. All branches of the chain share the ledger, and a call is charged
+//! to it under its lock, so parallel branches never overspend it between
+//! them.
+//!
+//! A call is refused with [`Error::ToolLimitExceeded`] when it would make
+//! more calls than `max_invocations`, send more parameter bytes than
+//! `max_outbound_bytes` or reach more distinct URL domains than
+//! `max_domains`, or once the chain has waited `max_tool_time_ms` on tools.
+//! An admitted call may only run for what is left of that time. Refusals
+//! are final: the ledger never resets while the chain runs.
+//!
+//! The ledger is recorded on the chain's report after every call, and every
+//! call is written to the audit log with the totals it left. A neuron
+//! isolated in a worker process keeps ledgers of its own there, limited by
+//! the chain's template or the defaults, which the chain's report does not
+//! show.
+
+use std::collections::BTreeSet;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use chrono::Utc;
+use dashmap::DashMap;
+use parking_lot::{Mutex, RwLock};
+use serde_json::Value;
+use tracing::{info, warn};
+
+use hal9_core::{
+    config::{ToolLimits, ToolSandboxConfig},
+    mcp::ToolRegistry,
+    metadata_schema::keys,
+    Error, NeuronSignal, Result,
+};
+
+use crate::chain_tracker::{ChainStatus, ChainTracker};
+
+pub use hal9_api_types::{ToolLimit, ToolUsage, ToolViolation};
+
+/// Metadata key of the template a chain was rendered from
+pub const TEMPLATE_NAME_KEY: &str = keys::template::NAME;
+
+/// How long a ledger of a chain the tracker doesn't know is kept after its
+/// last call
+const IDLE_LEDGER_TTL: Duration = Duration::from_secs(3600);
+
+/// A tool call, as charged to a ledger
+struct ToolCall<'a> {
+    signal: &'a NeuronSignal,
+    tool: &'a str,
+    outbound_bytes: u64,
+    domains: BTreeSet<String>,
+}
+
+impl<'a> ToolCall<'a> {
+    fn new(signal: &'a NeuronSignal, tool: &'a str, params: &Value) -> Self {
+        let mut domains = BTreeSet::new();
+        collect_domains(params, &mut domains);
+        Self {
+            signal,
+            tool,
+            outbound_bytes: serde_json::to_vec(params).map_or(0, |bytes| bytes.len() as u64),
+            domains,
+        }
+    }
+
+    fn violation(&self, limit: ToolLimit) -> ToolViolation {
+        ToolViolation {
+            signal_id: self.signal.signal_id.to_string(),
+            neuron_id: self.signal.to_neuron.clone(),
+            tool: self.tool.to_string(),
+            limit,
+            at: Utc::now(),
+        }
+    }
+}
+
+/// Hosts of the http(s) URLs among the strings of `params`
+fn collect_domains(params: &Value, domains: &mut BTreeSet<String>) {
+    match params {
+        Value::String(s) => {
+            let host = url::Url::parse(s)
+                .ok()
+                .filter(|url| matches!(url.scheme(), "http" | "https"))
+                .and_then(|url| url.host_str().map(str::to_string));
+            if let Some(host) = host {
+                domains.insert(host);
+            }
+        }
+        Value::Array(values) => values.iter().for_each(|value| collect_domains(value, domains)),
+        Value::Object(fields) => fields.values().for_each(|value| collect_domains(value, domains)),
+        _ => {}
+    }
+}
+
+#[derive(Default)]
+struct LedgerState {
+    invocations: u64,
+    outbound_bytes: u64,
+    domains: BTreeSet<String>,
+    tool_time: Duration,
+    violations: Vec<ToolViolation>,
+    last_call: Option<Instant>,
+}
+
+/// Tool calls of one chain against its limits
+pub struct ToolLedger {
+    chain_id: String,
+    limits: ToolLimits,
+    state: Mutex<LedgerState>,
+}
+
+impl ToolLedger {
+    pub fn new(chain_id: &str, limits: ToolLimits) -> Self {
+        Self {
+            chain_id: chain_id.to_string(),
+            limits,
+            state: Mutex::new(LedgerState::default()),
+        }
+    }
+
+    pub fn limits(&self) -> ToolLimits {
+        self.limits
+    }
+
+    /// Charge `call` to the ledger, or refuse it for the first limit it
+    /// would exceed. Returns how long the call may run, when tool time is
+    /// limited.
+    fn admit(&self, call: &ToolCall) -> Result<Option<Duration>> {
+        let mut state = self.state.lock();
+        state.last_call = Some(Instant::now());
+        let remaining = self.limits.max_tool_time_ms
+            .map(|max| Duration::from_millis(max).saturating_sub(state.tool_time));
+        let new_domains = call.domains.iter().filter(|domain| !state.domains.contains(*domain)).count();
+
+        let exceeded = if remaining == Some(Duration::ZERO) {
+            Some(ToolLimit::ToolTime)
+        } else if self.limits.max_invocations.is_some_and(|max| state.invocations >= max) {
+            Some(ToolLimit::Invocations)
+        } else if self.limits.max_outbound_bytes.is_some_and(|max| state.outbound_bytes + call.outbound_bytes > max) {
+            Some(ToolLimit::OutboundBytes)
+        } else if self.limits.max_domains.is_some_and(|max| state.domains.len() + new_domains > max as usize) {
+            Some(ToolLimit::Domains)
+        } else {
+            None
+        };
+        if let Some(limit) = exceeded {
+            let error = self.exceeded(&state, limit, call);
+            state.violations.push(call.violation(limit));
+            return Err(error);
+        }
+
+        state.invocations += 1;
+        state.outbound_bytes += call.outbound_bytes;
+        state.domains.extend(call.domains.iter().cloned());
+        Ok(remaining)
+    }
+
+    /// Charge the time an admitted call took
+    fn spend(&self, elapsed: Duration) {
+        self.state.lock().tool_time += elapsed;
+    }
+
+    /// Note that `call` ran out of the chain's tool time
+    fn cut_short(&self, call: &ToolCall) -> Error {
+        let mut state = self.state.lock();
+        let error = self.exceeded(&state, ToolLimit::ToolTime, call);
+        state.violations.push(call.violation(ToolLimit::ToolTime));
+        error
+    }
+
+    fn exceeded(&self, state: &LedgerState, limit: ToolLimit, call: &ToolCall) -> Error {
+        let message = match limit {
+            ToolLimit::Invocations => format!(
+                "{} of {} tool calls made",
+                state.invocations,
+                self.limits.max_invocations.unwrap_or_default()
+            ),
+            ToolLimit::OutboundBytes => format!(
+                "{} sends {} bytes with {} of {} sent",
+                call.tool,
+                call.outbound_bytes,
+                state.outbound_bytes,
+                self.limits.max_outbound_bytes.unwrap_or_default()
+            ),
+            ToolLimit::Domains => format!(
+                "{} reaches {} with {} of {} domains reached",
+                call.tool,
+                call.domains.iter().cloned().collect::<Vec<_>>().join(", "),
+                state.domains.len(),
+                self.limits.max_domains.unwrap_or_default()
+            ),
+            ToolLimit::ToolTime => format!(
+                "{} ms of {} ms spent in tools",
+                state.tool_time.as_millis(),
+                self.limits.max_tool_time_ms.unwrap_or_default()
+            ),
+        };
+        Error::ToolLimitExceeded {
+            chain_id: self.chain_id.clone(),
+            limit: limit.as_str().to_string(),
+            message,
+        }
+    }
+
+    /// The ledger as the chain's report shows it
+    pub fn usage(&self) -> ToolUsage {
+        let state = self.state.lock();
+        ToolUsage {
+            invocations: state.invocations,
+            outbound_bytes: state.outbound_bytes,
+            domains: state.domains.iter().cloned().collect(),
+            tool_time_ms: state.tool_time.as_millis() as u64,
+            max_invocations: self.limits.max_invocations,
+            max_outbound_bytes: self.limits.max_outbound_bytes,
+            max_domains: self.limits.max_domains,
+            max_tool_time_ms: self.limits.max_tool_time_ms,
+            violations: state.violations.clone(),
+        }
+    }
+
+    fn idle_for(&self, ttl: Duration) -> bool {
+        self.state.lock().last_call.is_none_or(|last| last.elapsed() >= ttl)
+    }
+}
+
+/// Ledgers of the running chains, consulted by neurons before every tool
+/// call
+pub struct ToolSandbox {
+    config: RwLock<ToolSandboxConfig>,
+    ledgers: DashMap<String, Arc<ToolLedger>>,
+    chain_tracker: Arc<ChainTracker>,
+}
+
+impl ToolSandbox {
+    /// Create a sandbox recording ledgers on the chains of `chain_tracker`
+    pub fn new(config: ToolSandboxConfig, chain_tracker: Arc<ChainTracker>) -> Self {
+        Self {
+            config: RwLock::new(config),
+            ledgers: DashMap::new(),
+            chain_tracker,
+        }
+    }
+
+    /// Apply changed limits to chains started from now on
+    pub fn set_config(&self, config: ToolSandboxConfig) {
+        *self.config.write() = config;
+    }
+
+    /// Limits of a chain submitted by `role` and rendered from `template`
+    pub fn limits_for(&self, role: Option<&str>, template: Option<&str>) -> ToolLimits {
+        let config = self.config.read();
+        let role = role.and_then(|role| config.roles.get(role)).copied().unwrap_or_default();
+        let template = template.and_then(|name| config.templates.get(name)).copied().unwrap_or_default();
+        template.or(role).or(config.default)
+    }
+
+    /// Open the ledger of the chain `chain_id` rooted at `root`, submitted
+    /// by `role`. Ledgers of chains that finished are dropped.
+    pub fn start(&self, chain_id: &str, root: &NeuronSignal, role: Option<&str>) {
+        self.ledgers.retain(|id, ledger| match self.chain_tracker.get(id) {
+            Some(record) => record.status == ChainStatus::Running,
+            None => !ledger.idle_for(IDLE_LEDGER_TTL),
+        });
+        let limits = self.limits_for(role, root.metadata.get(TEMPLATE_NAME_KEY).map(String::as_str));
+        self.ledgers.insert(chain_id.to_string(), Arc::new(ToolLedger::new(chain_id, limits)));
+    }
+
+    /// Ledger of a chain, once started or once it made a tool call
+    pub fn ledger(&self, chain_id: &str) -> Option<Arc<ToolLedger>> {
+        self.ledgers.get(chain_id).map(|ledger| ledger.clone())
+    }
+
+    /// Ledger of the chain `signal` belongs to. A chain this sandbox didn't
+    /// start, e.g. one submitted on a peer, gets its template's limits or
+    /// the defaults.
+    fn ledger_of(&self, signal: &NeuronSignal) -> Option<Arc<ToolLedger>> {
+        let chain_id = ChainTracker::chain_id_of(signal)?;
+        if let Some(ledger) = self.ledger(chain_id) {
+            return Some(ledger);
+        }
+        let limits = self.limits_for(None, signal.metadata.get(TEMPLATE_NAME_KEY).map(String::as_str));
+        let ledger = self.ledgers
+            .entry(chain_id.to_string())
+            .or_insert_with(|| Arc::new(ToolLedger::new(chain_id, limits)))
+            .clone();
+        Some(ledger)
+    }
+
+    /// Run `tool` from `registry` for the neuron processing `signal`,
+    /// charged to its chain's ledger. Signals of no chain are not limited.
+    pub async fn execute(
+        &self,
+        registry: &ToolRegistry,
+        signal: &NeuronSignal,
+        tool: &str,
+        params: Value,
+    ) -> Result<Value> {
+        let Some(ledger) = self.ledger_of(signal) else {
+            return registry.execute(tool, params).await;
+        };
+
+        let call = ToolCall::new(signal, tool, &params);
+        let result = match ledger.admit(&call) {
+            Ok(remaining) => {
+                let started = Instant::now();
+                let result = match remaining {
+                    Some(remaining) => match tokio::time::timeout(remaining, registry.execute(tool, params)).await {
+                        Ok(result) => result,
+                        Err(_) => Err(ledger.cut_short(&call)),
+                    },
+                    None => registry.execute(tool, params).await,
+                };
+                ledger.spend(started.elapsed());
+                result
+            }
+            Err(e) => Err(e),
+        };
+
+        let usage = ledger.usage();
+        let refused = match &result {
+            Err(Error::ToolLimitExceeded { limit, .. }) => Some(limit.as_str()),
+            _ => None,
+        };
+        if let Some(limit) = refused {
+            warn!(
+                target: "audit",
+                event = "tool_limit_exceeded",
+                chain_id = %ledger.chain_id,
+                signal_id = %signal.signal_id,
+                neuron_id = %signal.to_neuron,
+                tool,
+                limit,
+                invocations = usage.invocations,
+                outbound_bytes = usage.outbound_bytes,
+                domains = usage.domains.len(),
+                tool_time_ms = usage.tool_time_ms,
+                "Tool {} refused for chain {}: {} limit", tool, ledger.chain_id, limit
+            );
+        } else {
+            info!(
+                target: "audit",
+                event = "tool_call",
+                chain_id = %ledger.chain_id,
+                signal_id = %signal.signal_id,
+                neuron_id = %signal.to_neuron,
+                tool,
+                outbound_bytes = call.outbound_bytes,
+                ok = result.is_ok(),
+                invocations = usage.invocations,
+                total_outbound_bytes = usage.outbound_bytes,
+                domains = usage.domains.len(),
+                tool_time_ms = usage.tool_time_ms,
+                "Tool {} called for chain {}", tool, ledger.chain_id
+            );
+        }
+        self.chain_tracker.record_tools(&ledger.chain_id, usage);
+
+        result
+    }
+}
+
+/// Check that limits, when set, allow a tool call
+pub fn validate(config: &ToolSandboxConfig) -> Result<()> {
+    let sections = std::iter::once(("default".to_string(), &config.default))
+        .chain(config.roles.iter().map(|(role, limits)| (format!("roles.{}", role), limits)))
+        .chain(config.templates.iter().map(|(name, limits)| (format!("templates.{}", name), limits)));
+    for (section, limits) in sections {
+        let zero = [
+            ("max_invocations", limits.max_invocations == Some(0)),
+            ("max_outbound_bytes", limits.max_outbound_bytes == Some(0)),
+            ("max_tool_time_ms", limits.max_tool_time_ms == Some(0)),
+        ];
+        if let Some((field, _)) = zero.iter().find(|(_, zero)| *zero) {
+            return Err(Error::Config(format!(
+                "Invalid tool_sandbox.{}: {} must be positive",
+                section, field
+            )));
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,548 @@
+//! Topology patches
+//!
+//! Operators edit the neuron topology with patches instead of hand-editing
+//! YAML. A patch is a list of operations:
+//!
+//! ```json
+//! {"ops": [
+//!   {"op": "add_neuron", "neuron": {"id": "reviewer", "layer": "L3", "forward_connections": [], "backward_connections": []}},
+//!   {"op": "set_connections", "id": "planner", "forward_connections": ["coder", "reviewer"]},
+//!   {"op": "set_prompt", "id": "reviewer", "system_prompt": "Review the design"},
+//!   {"op": "remove_neuron", "id": "legacy"}
+//! ]}
+//! ```
+//!
+//! Proposing a patch checks the resulting topology against every rule in
+//! [`RULES`] and returns it with a diff and a token; nothing changes until
+//! the token is applied. Tokens are single use, expire after
+//! [`PATCH_TOKEN_TTL`], and are void once another patch lands first. Every
+//! applied patch keeps its inverse, so the latest ones can be reverted in
+//! turn.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::time::Instant;
+use tracing::info;
+use uuid::Uuid;
+
+use hal9_core::config::ValidationConfig;
+use hal9_core::{Layer, NeuronConfig};
+
+use crate::concurrency::ConcurrencyConfig;
+use crate::error::{ServerError, ServerResult};
+use crate::router::policy::RoutingPolicy;
+use crate::validation::ValidationPipeline;
+
+/// How long a proposed patch can be applied
+pub const PATCH_TOKEN_TTL: Duration = Duration::from_secs(600);
+
+/// Applied patches that can still be reverted; older ones are forgotten
+pub const MAX_REVERTIBLE_PATCHES: usize = 50;
+
+/// Static rules every topology must satisfy, by ID
+pub const RULES: &[(&str, &str)] = &[
+    ("non-empty", "The topology has at least one neuron"),
+    ("unique-ids", "Neuron IDs are unique and not empty"),
+    ("known-layer", "Every neuron is on one of the layers L1 to L9"),
+    ("known-connections", "Forward and backward connections name neurons of the topology"),
+    ("no-self-connections", "No neuron connects to itself"),
+    ("neuron-settings", "A neuron's concurrency and validation settings are valid"),
+    ("known-fallback", "Overflow is shed to a neuron of the topology"),
+    ("routing-groups", "Routing groups resolve to forward connections and are not neuron IDs"),
+];
+
+/// One operation of a patch
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum PatchOp {
+    AddNeuron {
+        neuron: NeuronConfig,
+    },
+    RemoveNeuron {
+        id: String,
+    },
+    /// Replace a neuron's connections; an omitted list is left as is
+    SetConnections {
+        id: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        forward_connections: Option<Vec<String>>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        backward_connections: Option<Vec<String>>,
+    },
+    /// Replace a neuron's system prompt; `null` falls back to the layer prompt
+    SetPrompt {
+        id: String,
+        system_prompt: Option<String>,
+    },
+}
+
+/// A broken rule
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RuleViolation {
+    /// ID of the rule in [`RULES`]
+    pub rule: &'static str,
+    pub neuron: Option<String>,
+    pub message: String,
+}
+
+impl fmt::Display for RuleViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}] {}", self.rule, self.message)
+    }
+}
+
+/// A value that differs between two topologies. `path` is
+/// `neurons.<id>` for added and removed neurons and
+/// `neurons.<id>.<field>` for changed fields.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DiffEntry {
+    pub path: String,
+    pub before: Option<Value>,
+    pub after: Option<Value>,
+}
+
+/// A validated patch awaiting its token
+#[derive(Debug, Clone, Serialize)]
+pub struct PatchProposal {
+    pub token: String,
+    pub expires_at: DateTime<Utc>,
+    /// The topology once applied
+    pub topology: Vec<NeuronConfig>,
+    pub diff: Vec<DiffEntry>,
+}
+
+/// A patch that was applied or reverted
+#[derive(Debug, Clone, Serialize)]
+pub struct AppliedPatch {
+    /// Counts every applied and reverted patch
+    pub version: u64,
+    pub diff: Vec<DiffEntry>,
+    /// Patches that can still be reverted
+    pub revertible: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum PatchKind {
+    Apply,
+    Revert,
+}
+
+/// A validated change taken out of the editor, to be put in place by the
+/// caller and then committed
+#[derive(Debug)]
+pub struct PreparedPatch {
+    kind: PatchKind,
+    base_version: u64,
+    inverse: Vec<PatchOp>,
+    pub before: Vec<NeuronConfig>,
+    pub after: Vec<NeuronConfig>,
+    pub diff: Vec<DiffEntry>,
+}
+
+impl PreparedPatch {
+    /// Neurons that are new or differ in any way, which need rebuilding
+    pub fn changed(&self) -> Vec<&NeuronConfig> {
+        let before: HashMap<&str, Value> = self.before.iter().map(|n| (n.id.as_str(), neuron_value(n))).collect();
+        self.after
+            .iter()
+            .filter(|n| before.get(n.id.as_str()) != Some(&neuron_value(n)))
+            .collect()
+    }
+
+    /// IDs of neurons that are gone
+    pub fn removed(&self) -> Vec<&str> {
+        self.before
+            .iter()
+            .filter(|old| !self.after.iter().any(|n| n.id == old.id))
+            .map(|old| old.id.as_str())
+            .collect()
+    }
+}
+
+struct Pending {
+    base_version: u64,
+    expires_at: Instant,
+    inverse: Vec<PatchOp>,
+    after: Vec<NeuronConfig>,
+    diff: Vec<DiffEntry>,
+}
+
+struct EditorState {
+    neurons: Vec<NeuronConfig>,
+    version: u64,
+    pending: HashMap<String, Pending>,
+    /// Inverses of applied patches, latest last
+    history: Vec<Vec<PatchOp>>,
+}
+
+/// The current topology with its proposed and applied patches
+pub struct TopologyEditor {
+    validation: HashMap<String, ValidationConfig>,
+    token_ttl: Duration,
+    state: Mutex<EditorState>,
+}
+
+impl TopologyEditor {
+    /// `validation` holds the layer validation configs that neurons without
+    /// their own `settings.validation` use
+    pub fn new(neurons: Vec<NeuronConfig>, validation: HashMap<String, ValidationConfig>, token_ttl: Duration) -> Self {
+        Self {
+            validation,
+            token_ttl,
+            state: Mutex::new(EditorState {
+                neurons,
+                version: 0,
+                pending: HashMap::new(),
+                history: Vec::new(),
+            }),
+        }
+    }
+
+    /// The current topology
+    pub fn neurons(&self) -> Vec<NeuronConfig> {
+        self.state.lock().neurons.clone()
+    }
+
+    /// Check a topology against every rule
+    pub fn validate(&self, neurons: &[NeuronConfig]) -> Vec<RuleViolation> {
+        validate_topology(neurons, &self.validation)
+    }
+
+    /// Validate a patch and hold it under a new token
+    pub fn propose(&self, ops: Vec<PatchOp>) -> ServerResult<PatchProposal> {
+        if ops.is_empty() {
+            return Err(ServerError::InvalidInput("Topology patch has no operations".to_string()));
+        }
+        let mut state = self.state.lock();
+        let (after, inverse) = apply_ops(&state.neurons, &ops)?;
+        self.check(&after)?;
+        let diff = diff(&state.neurons, &after);
+
+        let now = Instant::now();
+        state.pending.retain(|_, pending| pending.expires_at > now);
+        let token = Uuid::new_v4().to_string();
+        let expires_at = Utc::now() + chrono::Duration::from_std(self.token_ttl).unwrap_or_else(|_| chrono::Duration::days(1));
+        let proposal = PatchProposal { token: token.clone(), expires_at, topology: after.clone(), diff: diff.clone() };
+        let base_version = state.version;
+        state.pending.insert(token, Pending { base_version, expires_at: now + self.token_ttl, inverse, after, diff });
+        Ok(proposal)
+    }
+
+    /// Take the patch proposed under `token` for applying
+    pub fn take(&self, token: &str) -> ServerResult<PreparedPatch> {
+        let mut state = self.state.lock();
+        let pending = state
+            .pending
+            .remove(token)
+            .ok_or_else(|| ServerError::NotFound(format!("No proposed topology patch with token {}", token)))?;
+        if pending.expires_at <= Instant::now() {
+            return Err(ServerError::InvalidInput(format!(
+                "Topology patch token {} expired; propose the patch again",
+                token
+            )));
+        }
+        if pending.base_version != state.version {
+            return Err(ServerError::InvalidInput(format!(
+                "The topology changed since patch {} was proposed; propose it again",
+                token
+            )));
+        }
+        Ok(PreparedPatch {
+            kind: PatchKind::Apply,
+            base_version: pending.base_version,
+            inverse: pending.inverse,
+            before: state.neurons.clone(),
+            after: pending.after,
+            diff: pending.diff,
+        })
+    }
+
+    /// Take the inverse of the latest applied patch for applying
+    pub fn take_revert(&self) -> ServerResult<PreparedPatch> {
+        let state = self.state.lock();
+        let inverse = state
+            .history
+            .last()
+            .ok_or_else(|| ServerError::NotFound("No applied topology patch to revert".to_string()))?;
+        let (after, _) = apply_ops(&state.neurons, inverse)?;
+        self.check(&after)?;
+        Ok(PreparedPatch {
+            kind: PatchKind::Revert,
+            base_version: state.version,
+            inverse: Vec::new(),
+            diff: diff(&state.neurons, &after),
+            before: state.neurons.clone(),
+            after,
+        })
+    }
+
+    /// Record a prepared patch as in place
+    pub fn commit(&self, patch: PreparedPatch, actor: Option<&str>) -> ServerResult<AppliedPatch> {
+        let mut state = self.state.lock();
+        if patch.base_version != state.version {
+            return Err(ServerError::Internal("Topology changed while a patch was being applied".to_string()));
+        }
+        match patch.kind {
+            PatchKind::Apply => {
+                state.history.push(patch.inverse);
+                if state.history.len() > MAX_REVERTIBLE_PATCHES {
+                    state.history.remove(0);
+                }
+            }
+            PatchKind::Revert => {
+                state.history.pop();
+            }
+        }
+        state.neurons = patch.after;
+        state.version += 1;
+
+        let (event, verb) = match patch.kind {
+            PatchKind::Apply => ("topology_patched", "applied"),
+            PatchKind::Revert => ("topology_reverted", "reverted"),
+        };
+        info!(
+            target: "audit",
+            event,
+            actor = actor.unwrap_or("unknown"),
+            version = state.version,
+            diff = %serde_json::to_string(&patch.diff).unwrap_or_default(),
+            "Topology patch {} ({} changes)", verb, patch.diff.len()
+        );
+        Ok(AppliedPatch { version: state.version, diff: patch.diff, revertible: state.history.len() })
+    }
+
+    fn check(&self, neurons: &[NeuronConfig]) -> ServerResult<()> {
+        let violations = self.validate(neurons);
+        if violations.is_empty() {
+            return Ok(());
+        }
+        let broken: Vec<String> = violations.iter().map(ToString::to_string).collect();
+        Err(ServerError::InvalidInput(format!(
+            "Topology patch breaks {} rule(s): {}",
+            violations.len(),
+            broken.join("; ")
+        )))
+    }
+}
+
+/// Check a topology against every rule in [`RULES`]
+pub fn validate_topology(
+    neurons: &[NeuronConfig],
+    validation: &HashMap<String, ValidationConfig>,
+) -> Vec<RuleViolation> {
+    let mut violations = Vec::new();
+    let mut violation = |rule: &'static str, neuron: &str, message: String| {
+        violations.push(RuleViolation { rule, neuron: Some(neuron.to_string()), message });
+    };
+
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for neuron in neurons {
+        *counts.entry(neuron.id.as_str()).or_default() += 1;
+    }
+    let mut reported = HashSet::new();
+
+    for neuron in neurons {
+        let id = neuron.id.as_str();
+        if id.is_empty() {
+            violation("unique-ids", id, "A neuron has an empty ID".to_string());
+        } else if counts[id] > 1 && reported.insert(id) {
+            violation("unique-ids", id, format!("{} neurons have the ID {}", counts[id], id));
+        }
+
+        let layer = Layer::from_str(&neuron.layer);
+        if layer.is_none() {
+            violation("known-layer", id, format!("Neuron {} is on unknown layer '{}'", id, neuron.layer));
+        }
+
+        for (kind, targets) in [("forwards", &neuron.forward_connections), ("sends errors", &neuron.backward_connections)] {
+            for target in targets {
+                if target == id {
+                    violation("no-self-connections", id, format!("Neuron {} connects to itself", id));
+                } else if !counts.contains_key(target.as_str()) {
+                    violation("known-connections", id, format!("Neuron {} {} to unknown neuron {}", id, kind, target));
+                }
+            }
+        }
+
+        if let Some(layer) = layer {
+            match ConcurrencyConfig::for_neuron(neuron, layer) {
+                Ok(concurrency) => {
+                    if let Some(fallback) = &concurrency.fallback {
+                        if fallback != id && !counts.contains_key(fallback.as_str()) {
+                            violation("known-fallback", id, format!("Neuron {} sheds to unknown neuron {}", id, fallback));
+                        }
+                    }
+                }
+                Err(e) => violation("neuron-settings", id, e.to_string()),
+            }
+        }
+        if let Err(e) = ValidationPipeline::for_neuron(validation, neuron) {
+            violation("neuron-settings", id, e.to_string());
+        }
+        match RoutingPolicy::for_neuron(neuron) {
+            Ok(Some(policy)) if counts.contains_key(policy.address()) => violation(
+                "routing-groups",
+                id,
+                format!("Neuron {} has routing group {}, which is also a neuron ID", id, policy.address()),
+            ),
+            Ok(_) => {}
+            Err(e) => violation("routing-groups", id, e.to_string()),
+        }
+    }
+
+    if neurons.is_empty() {
+        violations.push(RuleViolation {
+            rule: "non-empty",
+            neuron: None,
+            message: "The topology has no neurons".to_string(),
+        });
+    }
+    violations
+}
+
+/// Changes from one topology to another, neuron by neuron
+pub fn diff(before: &[NeuronConfig], after: &[NeuronConfig]) -> Vec<DiffEntry> {
+    let old: HashMap<&str, Value> = before.iter().map(|n| (n.id.as_str(), neuron_value(n))).collect();
+    let new: HashMap<&str, Value> = after.iter().map(|n| (n.id.as_str(), neuron_value(n))).collect();
+    let mut entries = Vec::new();
+
+    for neuron in after {
+        let id = neuron.id.as_str();
+        let current = &new[id];
+        match old.get(id) {
+            None => entries.push(DiffEntry {
+                path: format!("neurons.{}", id),
+                before: None,
+                after: Some(current.clone()),
+            }),
+            Some(previous) => {
+                let (Some(previous), Some(current)) = (previous.as_object(), current.as_object()) else {
+                    continue;
+                };
+                for (field, value) in current {
+                    let was = previous.get(field).unwrap_or(&Value::Null);
+                    if was != value {
+                        entries.push(DiffEntry {
+                            path: format!("neurons.{}.{}", id, field),
+                            before: Some(was.clone()),
+                            after: Some(value.clone()),
+                        });
+                    }
+                }
+            }
+        }
+    }
+    for neuron in before {
+        if !new.contains_key(neuron.id.as_str()) {
+            entries.push(DiffEntry {
+                path: format!("neurons.{}", neuron.id),
+                before: Some(old[neuron.id.as_str()].clone()),
+                after: None,
+            });
+        }
+    }
+    entries
+}
+
+/// Operations turning `before` into `after`, to apply a topology edited as
+/// a whole through a patch. A neuron whose layer, command or settings
+/// changed is removed and added again, as patches cannot edit those.
+pub fn ops_between(before: &[NeuronConfig], after: &[NeuronConfig]) -> Vec<PatchOp> {
+    let old: HashMap<&str, &NeuronConfig> = before.iter().map(|n| (n.id.as_str(), n)).collect();
+    let rebuilt = |neuron: &NeuronConfig, previous: &NeuronConfig| {
+        neuron.layer != previous.layer
+            || neuron.claude_command != previous.claude_command
+            || neuron.settings != previous.settings
+    };
+    let mut removals = Vec::new();
+    let mut additions = Vec::new();
+    let mut edits = Vec::new();
+
+    for neuron in before {
+        if !after.iter().any(|n| n.id == neuron.id) {
+            removals.push(PatchOp::RemoveNeuron { id: neuron.id.clone() });
+        }
+    }
+    for neuron in after {
+        let Some(previous) = old.get(neuron.id.as_str()) else {
+            additions.push(PatchOp::AddNeuron { neuron: neuron.clone() });
+            continue;
+        };
+        if rebuilt(neuron, previous) {
+            removals.push(PatchOp::RemoveNeuron { id: neuron.id.clone() });
+            additions.push(PatchOp::AddNeuron { neuron: neuron.clone() });
+            continue;
+        }
+        let forward = neuron.forward_connections != previous.forward_connections;
+        let backward = neuron.backward_connections != previous.backward_connections;
+        if forward || backward {
+            edits.push(PatchOp::SetConnections {
+                id: neuron.id.clone(),
+                forward_connections: forward.then(|| neuron.forward_connections.clone()),
+                backward_connections: backward.then(|| neuron.backward_connections.clone()),
+            });
+        }
+        if neuron.system_prompt != previous.system_prompt {
+            edits.push(PatchOp::SetPrompt { id: neuron.id.clone(), system_prompt: neuron.system_prompt.clone() });
+        }
+    }
+    removals.into_iter().chain(additions).chain(edits).collect()
+}
+
+fn neuron_value(neuron: &NeuronConfig) -> Value {
+    serde_json::to_value(neuron).unwrap_or(Value::Null)
+}
+
+/// Apply `ops` in order, returning the new topology and the operations
+/// undoing them
+fn apply_ops(neurons: &[NeuronConfig], ops: &[PatchOp]) -> ServerResult<(Vec<NeuronConfig>, Vec<PatchOp>)> {
+    let mut neurons = neurons.to_vec();
+    let mut inverse = Vec::with_capacity(ops.len());
+    for (i, op) in ops.iter().enumerate() {
+        let undo = apply_op(&mut neurons, op).map_err(|e| ServerError::InvalidInput(format!("ops[{}]: {}", i, e)))?;
+        inverse.push(undo);
+    }
+    inverse.reverse();
+    Ok((neurons, inverse))
+}
+
+fn apply_op(neurons: &mut Vec<NeuronConfig>, op: &PatchOp) -> Result<PatchOp, String> {
+    let missing = |id: &str| format!("No neuron with ID {}", id);
+    match op {
+        PatchOp::AddNeuron { neuron } => {
+            neurons.push(neuron.clone());
+            Ok(PatchOp::RemoveNeuron { id: neuron.id.clone() })
+        }
+        PatchOp::RemoveNeuron { id } => {
+            let index = neurons.iter().position(|n| &n.id == id).ok_or_else(|| missing(id))?;
+            let removed = neurons.remove(index);
+            Ok(PatchOp::AddNeuron { neuron: removed })
+        }
+        PatchOp::SetConnections { id, forward_connections, backward_connections } => {
+            let neuron = neurons.iter_mut().find(|n| &n.id == id).ok_or_else(|| missing(id))?;
+            let undo = PatchOp::SetConnections {
+                id: id.clone(),
+                forward_connections: forward_connections.as_ref().map(|_| neuron.forward_connections.clone()),
+                backward_connections: backward_connections.as_ref().map(|_| neuron.backward_connections.clone()),
+            };
+            if let Some(forward) = forward_connections {
+                neuron.forward_connections = forward.clone();
+            }
+            if let Some(backward) = backward_connections {
+                neuron.backward_connections = backward.clone();
+            }
+            Ok(undo)
+        }
+        PatchOp::SetPrompt { id, system_prompt } => {
+            let neuron = neurons.iter_mut().find(|n| &n.id == id).ok_or_else(|| missing(id))?;
+            let previous = std::mem::replace(&mut neuron.system_prompt, system_prompt.clone());
+            Ok(PatchOp::SetPrompt { id: id.clone(), system_prompt: previous })
+        }
+    }
+}
@@ -0,0 +1,420 @@
+//! Response validation run before a neuron's signals are forwarded
+//!
+//! Validators are configured per layer in `ServerConfig.validation`, or per
+//! neuron under `settings.validation`. When a response fails, the neuron is
+//! re-prompted with the failure reason up to `max_retries` times before the
+//! signal fails. The outcome is written to the metadata of the signals the
+//! response produces (`validation_status`, `validation_reason`,
+//! `validation_retries`).
+
+use std::collections::HashMap;
+
+use jsonschema::JSONSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tree_sitter::{Language, Node, Parser};
+
+use hal9_core::{
+    config::{ValidationConfig, ValidatorConfig},
+    metadata_schema::keys,
+    Error, NeuronConfig, Result,
+};
+
+/// Signal metadata key holding "passed" or "failed"
+pub const VALIDATION_STATUS_KEY: &str = keys::validation::STATUS;
+/// Signal metadata key holding the last failure reason
+pub const VALIDATION_REASON_KEY: &str = keys::validation::REASON;
+/// Signal metadata key holding how many retries were needed
+pub const VALIDATION_RETRIES_KEY: &str = keys::validation::RETRIES;
+
+/// A check applied to a neuron's raw response
+pub trait ResponseValidator: Send + Sync {
+    /// Name used in metrics and failure reasons
+    fn name(&self) -> &'static str;
+
+    /// `Err` carries a reason the neuron can act on
+    fn validate(&self, response: &str) -> std::result::Result<(), String>;
+}
+
+/// A fenced code block in a response
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CodeBlock {
+    /// Info string after the opening fence, lowercased; empty if none
+    pub language: String,
+    pub code: String,
+    /// 1-based line of the opening fence
+    pub line: usize,
+}
+
+/// Extract the fenced code blocks of a response. An unterminated block runs
+/// to the end of the response.
+pub fn code_blocks(response: &str) -> Vec<CodeBlock> {
+    let mut blocks = Vec::new();
+    let mut open: Option<CodeBlock> = None;
+
+    for (index, line) in response.lines().enumerate() {
+        let trimmed = line.trim_start();
+        match open.as_mut() {
+            None => {
+                if let Some(info) = trimmed.strip_prefix("```") {
+                    open = Some(CodeBlock {
+                        language: info.split_whitespace().next().unwrap_or("").to_lowercase(),
+                        code: String::new(),
+                        line: index + 1,
+                    });
+                }
+            }
+            Some(block) => {
+                if trimmed.starts_with("```") {
+                    blocks.extend(open.take());
+                } else {
+                    block.code.push_str(line);
+                    block.code.push('\n');
+                }
+            }
+        }
+    }
+
+    blocks.extend(open);
+    blocks
+}
+
+/// Tree-sitter grammar for a code block language
+fn grammar(language: &str) -> Option<Language> {
+    match language {
+        "rust" | "rs" => Some(tree_sitter_rust::language()),
+        "python" | "py" | "python3" => Some(tree_sitter_python::language()),
+        "javascript" | "js" | "jsx" | "node" => Some(tree_sitter_javascript::language()),
+        _ => None,
+    }
+}
+
+/// First ERROR or MISSING node below `node`, in source order
+fn first_error(node: Node) -> Option<Node> {
+    if node.is_error() || node.is_missing() {
+        return Some(node);
+    }
+    if !node.has_error() {
+        return None;
+    }
+    let mut cursor = node.walk();
+    let children: Vec<Node> = node.children(&mut cursor).collect();
+    children.into_iter().find_map(first_error)
+}
+
+/// Parses fenced code blocks with tree-sitter
+pub struct CodeSyntaxValidator {
+    /// Languages checked; empty checks every supported language
+    languages: Vec<String>,
+}
+
+impl CodeSyntaxValidator {
+    /// Fails for languages without a grammar
+    pub fn new(languages: Vec<String>) -> Result<Self> {
+        let languages: Vec<String> = languages.into_iter().map(|l| l.to_lowercase()).collect();
+        if let Some(unsupported) = languages.iter().find(|l| grammar(l).is_none()) {
+            return Err(Error::Config(format!(
+                "No syntax checker for language '{}'; expected rust, python or javascript",
+                unsupported
+            )));
+        }
+        Ok(Self { languages })
+    }
+
+    fn checks(&self, language: &str) -> bool {
+        self.languages.is_empty() || self.languages.iter().any(|l| l == language)
+    }
+
+    fn check_block(&self, block: &CodeBlock, language: Language) -> std::result::Result<(), String> {
+        let mut parser = Parser::new();
+        parser
+            .set_language(language)
+            .map_err(|e| format!("{} grammar failed to load: {}", block.language, e))?;
+        let tree = parser
+            .parse(&block.code, None)
+            .ok_or_else(|| format!("{} code block at line {} could not be parsed", block.language, block.line))?;
+
+        match first_error(tree.root_node()) {
+            None => Ok(()),
+            Some(node) => {
+                let line = block.line + node.start_position().row + 1;
+                let problem = if node.is_missing() {
+                    format!("missing `{}`", node.kind())
+                } else {
+                    let text = block.code[node.byte_range()].lines().next().unwrap_or("").trim();
+                    let text: String = text.chars().take(40).collect();
+                    format!("unexpected `{}`", text)
+                };
+                Err(format!(
+                    "{} code block has a syntax error at line {}: {}",
+                    block.language, line, problem
+                ))
+            }
+        }
+    }
+}
+
+impl ResponseValidator for CodeSyntaxValidator {
+    fn name(&self) -> &'static str {
+        "code_syntax"
+    }
+
+    fn validate(&self, response: &str) -> std::result::Result<(), String> {
+        for block in code_blocks(response) {
+            if !self.checks(&block.language) {
+                continue;
+            }
+            // Blocks in languages without a grammar are not checked
+            if let Some(language) = grammar(&block.language) {
+                self.check_block(&block, language)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Structured part of a response: the first ```json block, else the
+/// `CONTENT:` section, else the whole response
+pub fn structured_output(response: &str) -> String {
+    if let Some(block) = code_blocks(response).into_iter().find(|b| b.language == "json") {
+        return block.code;
+    }
+    if response.lines().any(|l| l.starts_with("CONTENT:")) {
+        return response
+            .lines()
+            .skip_while(|l| !l.starts_with("CONTENT:"))
+            .skip(1)
+            .collect::<Vec<_>>()
+            .join("\n");
+    }
+    response.to_string()
+}
+
+/// Validates structured output against a JSON Schema
+pub struct JsonSchemaValidator {
+    schema: JSONSchema,
+}
+
+impl JsonSchemaValidator {
+    /// Fails if `schema` is not a valid JSON Schema
+    pub fn new(schema: &Value) -> Result<Self> {
+        let schema = JSONSchema::compile(schema)
+            .map_err(|e| Error::Config(format!("Invalid JSON schema: {}", e)))?;
+        Ok(Self { schema })
+    }
+}
+
+impl ResponseValidator for JsonSchemaValidator {
+    fn name(&self) -> &'static str {
+        "json_schema"
+    }
+
+    fn validate(&self, response: &str) -> std::result::Result<(), String> {
+        let output = structured_output(response);
+        let instance: Value = serde_json::from_str(output.trim())
+            .map_err(|e| format!("output is not valid JSON: {}", e))?;
+
+        if let Err(errors) = self.schema.validate(&instance) {
+            let errors: Vec<String> = errors
+                .take(3)
+                .map(|e| {
+                    let path = e.instance_path.to_string();
+                    if path.is_empty() {
+                        e.to_string()
+                    } else {
+                        format!("{}: {}", path, e)
+                    }
+                })
+                .collect();
+            return Err(format!("output does not match the JSON schema: {}", errors.join("; ")));
+        }
+        Ok(())
+    }
+}
+
+/// Rejects responses over a character limit
+pub struct MaxLengthValidator {
+    max_chars: usize,
+}
+
+impl MaxLengthValidator {
+    pub fn new(max_chars: usize) -> Self {
+        Self { max_chars }
+    }
+}
+
+impl ResponseValidator for MaxLengthValidator {
+    fn name(&self) -> &'static str {
+        "max_length"
+    }
+
+    fn validate(&self, response: &str) -> std::result::Result<(), String> {
+        let length = response.chars().count();
+        if length > self.max_chars {
+            return Err(format!(
+                "response is {} characters, over the limit of {}",
+                length, self.max_chars
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Rejects responses with a code fence that is never closed
+pub struct CodeFenceValidator;
+
+impl ResponseValidator for CodeFenceValidator {
+    fn name(&self) -> &'static str {
+        "code_fences"
+    }
+
+    fn validate(&self, response: &str) -> std::result::Result<(), String> {
+        let mut open = None;
+        for (index, line) in response.lines().enumerate() {
+            if line.trim_start().starts_with("```") {
+                open = match open {
+                    None => Some(index + 1),
+                    Some(_) => None,
+                };
+            }
+        }
+        match open {
+            None => Ok(()),
+            Some(line) => Err(format!("code block opened at line {} is never closed", line)),
+        }
+    }
+}
+
+/// Rejects markdown markup: code fences, headings, bold text and links
+pub struct PlainTextValidator;
+
+impl ResponseValidator for PlainTextValidator {
+    fn name(&self) -> &'static str {
+        "plain_text"
+    }
+
+    fn validate(&self, response: &str) -> std::result::Result<(), String> {
+        for (index, line) in response.lines().enumerate() {
+            let trimmed = line.trim_start();
+            let markup = if trimmed.starts_with("```") {
+                "a code fence"
+            } else if trimmed.starts_with('#') && trimmed.trim_start_matches('#').starts_with(' ') {
+                "a heading"
+            } else if line.contains("**") {
+                "bold text"
+            } else if line.contains("](") {
+                "a link"
+            } else {
+                continue;
+            };
+            return Err(format!("line {} contains markdown: {}", index + 1, markup));
+        }
+        Ok(())
+    }
+}
+
+/// One validator's verdict
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidatorResult {
+    pub validator: &'static str,
+    /// `None` when the response passed
+    pub failure: Option<String>,
+}
+
+/// Outcome of validating a neuron's response, retries included
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ValidationReport {
+    pub passed: bool,
+    /// Reason the last failing attempt was rejected
+    pub reason: Option<String>,
+    pub retries: u32,
+}
+
+impl ValidationReport {
+    /// Write the report into signal metadata
+    pub fn apply_to(&self, metadata: &mut HashMap<String, String>) {
+        let status = if self.passed { "passed" } else { "failed" };
+        metadata.insert(VALIDATION_STATUS_KEY.to_string(), status.to_string());
+        metadata.insert(VALIDATION_RETRIES_KEY.to_string(), self.retries.to_string());
+        match &self.reason {
+            Some(reason) => {
+                metadata.insert(VALIDATION_REASON_KEY.to_string(), reason.clone());
+            }
+            None => {
+                metadata.remove(VALIDATION_REASON_KEY);
+            }
+        }
+    }
+}
+
+/// Validators configured for one neuron
+pub struct ValidationPipeline {
+    validators: Vec<Box<dyn ResponseValidator>>,
+    max_retries: u32,
+}
+
+impl ValidationPipeline {
+    pub fn new(validators: Vec<Box<dyn ResponseValidator>>, max_retries: u32) -> Self {
+        Self { validators, max_retries }
+    }
+
+    /// Build the configured validators
+    pub fn from_config(config: &ValidationConfig) -> Result<Self> {
+        let validators = config
+            .validators
+            .iter()
+            .map(|validator| -> Result<Box<dyn ResponseValidator>> {
+                Ok(match validator {
+                    ValidatorConfig::CodeSyntax { languages } => {
+                        Box::new(CodeSyntaxValidator::new(languages.clone())?)
+                    }
+                    ValidatorConfig::JsonSchema { schema } => Box::new(JsonSchemaValidator::new(schema)?),
+                    ValidatorConfig::MaxLength { max_chars } => Box::new(MaxLengthValidator::new(*max_chars)),
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self::new(validators, config.max_retries))
+    }
+
+    /// Pipeline for a neuron: its `settings.validation` if present, else its
+    /// layer's entry in `layers`. `None` when no validators apply.
+    pub fn for_neuron(layers: &HashMap<String, ValidationConfig>, neuron: &NeuronConfig) -> Result<Option<Self>> {
+        let config = match neuron.settings.get("validation") {
+            Some(settings) => serde_json::from_value::<ValidationConfig>(settings.clone()).map_err(|e| {
+                Error::Config(format!("Invalid validation settings for neuron {}: {}", neuron.id, e))
+            })?,
+            None => match layers.get(&neuron.layer) {
+                Some(config) => config.clone(),
+                None => return Ok(None),
+            },
+        };
+        if config.validators.is_empty() {
+            return Ok(None);
+        }
+        Self::from_config(&config).map(Some)
+    }
+
+    pub fn max_retries(&self) -> u32 {
+        self.max_retries
+    }
+
+    /// Run every validator against `response`
+    pub fn validate(&self, response: &str) -> Vec<ValidatorResult> {
+        self.validators
+            .iter()
+            .map(|validator| ValidatorResult {
+                validator: validator.name(),
+                failure: validator.validate(response).err(),
+            })
+            .collect()
+    }
+}
+
+/// Prompt asking the neuron to correct a rejected response
+pub fn retry_prompt(prompt: &str, response: &str, reason: &str) -> String {
+    format!(
+        "{}\n\nYOUR PREVIOUS OUTPUT:\n{}\n\nYour previous output failed validation because {}. \
+         Respond again with the problem fixed.",
+        prompt, response, reason
+    )
+}
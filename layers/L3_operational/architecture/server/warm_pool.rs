@@ -0,0 +1,286 @@
+//! Warm pools of pre-initialized neuron instances
+//!
+//! Some neurons are slow to start: a WASM plugin is compiled and
+//! instantiated, a process-backed neuron spawns its process. A pool keeps
+//! `warm_pool.sizes[type]` idle instances of such a type ready, so the first
+//! signal after a quiet spell doesn't pay the cold start. A checkout takes an
+//! idle instance (a hit) or, with the pool empty, instantiates one on demand
+//! (a miss); dropping the checkout hands the instance back.
+//!
+//! A background pass refills the pools and replaces instances idle for longer
+//! than `warm_pool.idle_ttl_secs`, bounding the memory long-lived instances
+//! accumulate. Checked-out instances are never recycled: their idle time
+//! starts when they come back.
+
+use async_trait::async_trait;
+use dashmap::DashMap;
+use parking_lot::{Mutex, RwLock};
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::Instant;
+use tracing::{debug, warn};
+
+use hal9_core::{config::WarmPoolConfig, Error, NeuronInterface, Result};
+
+use crate::metrics::Metrics;
+
+/// Creates instances of one neuron type
+#[async_trait]
+pub trait InstanceFactory: Send + Sync {
+    /// A started instance, ready for signals
+    async fn instantiate(&self) -> Result<Arc<dyn NeuronInterface>>;
+}
+
+/// Size and occupancy of one neuron type's pool
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct WarmPoolStatus {
+    pub neuron_type: String,
+    /// Idle instances the pool is refilled to
+    pub size: usize,
+    pub idle: usize,
+    pub in_use: usize,
+}
+
+/// Warm pools by neuron type
+pub struct WarmPool {
+    config: RwLock<WarmPoolConfig>,
+    pools: DashMap<String, Arc<TypePool>>,
+    metrics: Arc<RwLock<Option<Arc<Metrics>>>>,
+}
+
+/// Instances of one neuron type
+struct TypePool {
+    neuron_type: String,
+    factory: Arc<dyn InstanceFactory>,
+    /// Idle instances, longest idle first
+    idle: Mutex<VecDeque<IdleInstance>>,
+    size: AtomicUsize,
+    in_use: AtomicUsize,
+    metrics: Arc<RwLock<Option<Arc<Metrics>>>>,
+}
+
+struct IdleInstance {
+    neuron: Arc<dyn NeuronInterface>,
+    since: Instant,
+}
+
+impl WarmPool {
+    pub fn new(config: WarmPoolConfig) -> Self {
+        Self {
+            config: RwLock::new(config),
+            pools: DashMap::new(),
+            metrics: Arc::new(RwLock::new(None)),
+        }
+    }
+
+    /// Record hits, misses, recycling and instantiation latency
+    pub fn set_metrics(&self, metrics: Arc<Metrics>) {
+        *self.metrics.write() = Some(metrics);
+    }
+
+    /// Pool instances of `neuron_type` made by `factory`, replacing any
+    /// earlier factory and its idle instances
+    pub fn register(&self, neuron_type: &str, factory: Arc<dyn InstanceFactory>) {
+        let size = self.config.read().sizes.get(neuron_type).copied().unwrap_or(0);
+        let pool = Arc::new(TypePool {
+            neuron_type: neuron_type.to_string(),
+            factory,
+            idle: Mutex::new(VecDeque::new()),
+            size: AtomicUsize::new(size),
+            in_use: AtomicUsize::new(0),
+            metrics: self.metrics.clone(),
+        });
+        if let Some(previous) = self.pools.insert(neuron_type.to_string(), pool) {
+            let retired: Vec<_> = previous.idle.lock().drain(..).map(|idle| idle.neuron).collect();
+            spawn_shutdown(retired);
+        }
+    }
+
+    /// Apply new sizes and idle TTL. Shrunk pools shed their surplus on the
+    /// next refresh; grown pools fill up on it.
+    pub fn set_config(&self, config: &WarmPoolConfig) {
+        for pool in self.pools.iter() {
+            let size = config.sizes.get(pool.key()).copied().unwrap_or(0);
+            pool.size.store(size, Ordering::Relaxed);
+        }
+        *self.config.write() = config.clone();
+    }
+
+    /// Interval between background refreshes
+    pub fn refresh_interval(&self) -> Duration {
+        Duration::from_millis(self.config.read().refresh_interval_ms.max(10))
+    }
+
+    /// An instance of `neuron_type`: a warm one if the pool has any, else a
+    /// new one
+    pub async fn checkout(&self, neuron_type: &str) -> Result<PooledInstance> {
+        let pool = self.pools.get(neuron_type)
+            .map(|pool| pool.clone())
+            .ok_or_else(|| Error::NotFound(format!("No instance factory for neuron type {}", neuron_type)))?;
+
+        let warm = pool.idle.lock().pop_back();
+        let neuron = match warm {
+            Some(idle) => {
+                pool.record(|metrics| metrics.record_warm_pool_checkout(&pool.neuron_type, true));
+                idle.neuron
+            }
+            None => {
+                pool.record(|metrics| metrics.record_warm_pool_checkout(&pool.neuron_type, false));
+                debug!("Warm pool for {} is empty, instantiating on demand", pool.neuron_type);
+                pool.instantiate().await?
+            }
+        };
+        pool.in_use.fetch_add(1, Ordering::Relaxed);
+        pool.publish_idle();
+
+        Ok(PooledInstance { neuron: Some(neuron), pool })
+    }
+
+    /// Recycle instances idle past the TTL, shed surplus instances, and
+    /// refill every pool to its size
+    pub async fn refresh(&self) {
+        let ttl = Duration::from_secs(self.config.read().idle_ttl_secs);
+        let pools: Vec<_> = self.pools.iter().map(|pool| pool.value().clone()).collect();
+        for pool in pools {
+            pool.refresh(ttl).await;
+        }
+    }
+
+    /// Every pool, by neuron type
+    pub fn status(&self) -> Vec<WarmPoolStatus> {
+        let mut status: Vec<_> = self.pools.iter()
+            .map(|pool| WarmPoolStatus {
+                neuron_type: pool.neuron_type.clone(),
+                size: pool.size.load(Ordering::Relaxed),
+                idle: pool.idle.lock().len(),
+                in_use: pool.in_use.load(Ordering::Relaxed),
+            })
+            .collect();
+        status.sort_by(|a, b| a.neuron_type.cmp(&b.neuron_type));
+        status
+    }
+}
+
+impl TypePool {
+    fn record(&self, f: impl FnOnce(&Metrics)) {
+        if let Some(metrics) = self.metrics.read().as_ref() {
+            f(metrics);
+        }
+    }
+
+    fn publish_idle(&self) {
+        let idle = self.idle.lock().len();
+        self.record(|metrics| metrics.set_warm_pool_idle(&self.neuron_type, idle as u64));
+    }
+
+    async fn instantiate(&self) -> Result<Arc<dyn NeuronInterface>> {
+        let started = std::time::Instant::now();
+        let neuron = self.factory.instantiate().await?;
+        self.record(|metrics| metrics.record_warm_pool_instantiation(&self.neuron_type, started.elapsed()));
+        Ok(neuron)
+    }
+
+    async fn refresh(&self, ttl: Duration) {
+        let now = Instant::now();
+        let (expired, surplus) = {
+            let mut idle = self.idle.lock();
+            let mut expired = Vec::new();
+            while idle.front().is_some_and(|instance| now.duration_since(instance.since) >= ttl) {
+                expired.extend(idle.pop_front().map(|instance| instance.neuron));
+            }
+            let excess = idle.len().saturating_sub(self.size.load(Ordering::Relaxed));
+            let surplus: Vec<_> = idle.drain(..excess).map(|instance| instance.neuron).collect();
+            (expired, surplus)
+        };
+        if !expired.is_empty() {
+            debug!("Recycling {} idle {} instances", expired.len(), self.neuron_type);
+            self.record(|metrics| metrics.record_warm_pool_recycled(&self.neuron_type, expired.len() as u64));
+        }
+        for neuron in expired.into_iter().chain(surplus) {
+            shutdown(neuron).await;
+        }
+
+        let missing = self.size.load(Ordering::Relaxed).saturating_sub(self.idle.lock().len());
+        for _ in 0..missing {
+            match self.instantiate().await {
+                Ok(neuron) => self.idle.lock().push_back(IdleInstance { neuron, since: Instant::now() }),
+                Err(e) => {
+                    warn!("Failed to pre-initialize a {} instance: {}", self.neuron_type, e);
+                    break;
+                }
+            }
+        }
+        self.publish_idle();
+    }
+}
+
+/// An instance checked out of a warm pool, returned to it on drop
+pub struct PooledInstance {
+    neuron: Option<Arc<dyn NeuronInterface>>,
+    pool: Arc<TypePool>,
+}
+
+impl std::ops::Deref for PooledInstance {
+    type Target = dyn NeuronInterface;
+
+    fn deref(&self) -> &Self::Target {
+        self.neuron.as_deref().expect("instance is only taken on drop")
+    }
+}
+
+impl Drop for PooledInstance {
+    fn drop(&mut self) {
+        let Some(neuron) = self.neuron.take() else { return };
+        self.pool.in_use.fetch_sub(1, Ordering::Relaxed);
+
+        let surplus = {
+            let mut idle = self.pool.idle.lock();
+            if idle.len() < self.pool.size.load(Ordering::Relaxed) {
+                idle.push_back(IdleInstance { neuron, since: Instant::now() });
+                None
+            } else {
+                Some(neuron)
+            }
+        };
+        // On-demand instances beyond the pool's size are not kept
+        match surplus {
+            Some(neuron) => spawn_shutdown(vec![neuron]),
+            None => self.pool.publish_idle(),
+        }
+    }
+}
+
+async fn shutdown(neuron: Arc<dyn NeuronInterface>) {
+    if let Err(e) = neuron.shutdown().await {
+        warn!("Error shutting down pooled instance {}: {}", neuron.id(), e);
+    }
+}
+
+fn spawn_shutdown(neurons: Vec<Arc<dyn NeuronInterface>>) {
+    if neurons.is_empty() {
+        return;
+    }
+    match tokio::runtime::Handle::try_current() {
+        Ok(handle) => {
+            handle.spawn(async move {
+                for neuron in neurons {
+                    shutdown(neuron).await;
+                }
+            });
+        }
+        Err(_) => warn!("No runtime to shut down {} pooled instances on", neurons.len()),
+    }
+}
+
+/// Refill and recycle warm pools periodically
+pub async fn refresh_task(pool: Arc<WarmPool>) {
+    let mut interval = tokio::time::interval(pool.refresh_interval());
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+    loop {
+        interval.tick().await;
+        pool.refresh().await;
+    }
+}
@@ -0,0 +1,731 @@
+//! Outbound webhooks for chain lifecycle and operational events
+//!
+//! Subscribers register a URL, a signing secret and the events they care
+//! about. Each delivery is a JSON POST signed with HMAC-SHA256 over
+//! `"{timestamp}.{body}"`; receivers recompute the signature with their secret
+//! and compare it to the `X-HAL9-Signature` header. Failed deliveries are
+//! retried with exponential backoff and dead-lettered after the configured
+//! number of attempts. The most recent deliveries per webhook are kept for
+//! inspection, and finished event deliveries are announced on a broadcast
+//! channel, see [`WebhookManager::subscribe`].
+//!
+//! With authentication enabled, subscriptions are kept in the auth database
+//! and outlive restarts; otherwise they live only as long as the server.
+
+use chrono::{DateTime, TimeZone, Utc};
+use dashmap::DashMap;
+use hmac::{Hmac, Mac};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::Sha256;
+use sqlx::Row;
+use std::collections::HashMap;
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
+use tracing::{debug, warn};
+
+use hal9_core::config::WebhookConfig;
+use hal9_core::secrets::SecretString;
+use hal9_core::sqlite::SqlitePools;
+use crate::approvals::PendingApproval;
+use crate::chain_tracker::{ChainResult, ChainStatus};
+use crate::error::{ServerError, ServerResult};
+use crate::singularity::StageTransition;
+use crate::slo::SloAlert;
+
+/// Header carrying the event name
+pub const EVENT_HEADER: &str = "X-HAL9-Event";
+
+/// Header carrying the delivery ID
+pub const DELIVERY_HEADER: &str = "X-HAL9-Delivery";
+
+/// Header carrying the signing timestamp (unix seconds)
+pub const TIMESTAMP_HEADER: &str = "X-HAL9-Timestamp";
+
+/// Header carrying the `sha256=<hex>` signature
+pub const SIGNATURE_HEADER: &str = "X-HAL9-Signature";
+
+/// Event name used by test deliveries
+pub const TEST_EVENT: &str = "webhook.test";
+
+/// Deliveries kept per webhook
+const DELIVERY_HISTORY: usize = 100;
+
+/// Minimum interval between budget alerts of the same kind
+const BUDGET_ALERT_COOLDOWN: Duration = Duration::from_secs(3600);
+
+/// Events a webhook can subscribe to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum WebhookEvent {
+    #[serde(rename = "chain.completed")]
+    ChainCompleted,
+    #[serde(rename = "chain.failed")]
+    ChainFailed,
+    #[serde(rename = "chain.cancelled")]
+    ChainCancelled,
+    #[serde(rename = "budget.threshold")]
+    BudgetThreshold,
+    #[serde(rename = "neuron.quarantined")]
+    NeuronQuarantined,
+    #[serde(rename = "slo.burn_rate")]
+    SloBurnRate,
+    #[serde(rename = "singularity.stage_changed")]
+    SingularityStageChanged,
+    #[serde(rename = "approval.requested")]
+    ApprovalRequested,
+}
+
+impl WebhookEvent {
+    /// Wire name of the event
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WebhookEvent::ChainCompleted => "chain.completed",
+            WebhookEvent::ChainFailed => "chain.failed",
+            WebhookEvent::ChainCancelled => "chain.cancelled",
+            WebhookEvent::BudgetThreshold => "budget.threshold",
+            WebhookEvent::NeuronQuarantined => "neuron.quarantined",
+            WebhookEvent::SloBurnRate => "slo.burn_rate",
+            WebhookEvent::SingularityStageChanged => "singularity.stage_changed",
+            WebhookEvent::ApprovalRequested => "approval.requested",
+        }
+    }
+
+    /// Event announcing a chain that finished with `status`
+    pub fn for_chain_status(status: ChainStatus) -> Option<Self> {
+        match status {
+            ChainStatus::Running => None,
+            ChainStatus::Completed => Some(WebhookEvent::ChainCompleted),
+            ChainStatus::Failed => Some(WebhookEvent::ChainFailed),
+            ChainStatus::Cancelled => Some(WebhookEvent::ChainCancelled),
+        }
+    }
+}
+
+/// A webhook subscription
+#[derive(Debug, Clone, Serialize)]
+pub struct Webhook {
+    pub id: String,
+    /// Organization (or user, without an organization) owning the webhook
+    pub owner: String,
+    pub url: String,
+    pub events: Vec<WebhookEvent>,
+    pub description: Option<String>,
+    pub created_at: DateTime<Utc>,
+    #[serde(skip)]
+    secret: SecretString,
+}
+
+/// Request to register a webhook
+#[derive(Debug, Clone, Deserialize)]
+pub struct CreateWebhookRequest {
+    pub url: String,
+    /// Signing secret; generated if omitted
+    pub secret: Option<String>,
+    pub events: Vec<WebhookEvent>,
+    pub description: Option<String>,
+}
+
+/// A newly registered webhook; the secret is only ever returned here
+#[derive(Debug, Clone, Serialize)]
+pub struct CreatedWebhook {
+    #[serde(flatten)]
+    pub webhook: Webhook,
+    pub secret: String,
+}
+
+/// Delivery state
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeliveryStatus {
+    Pending,
+    Retrying,
+    Delivered,
+    DeadLettered,
+}
+
+/// A single delivery attempt
+#[derive(Debug, Clone, Serialize)]
+pub struct DeliveryAttempt {
+    pub attempt: u32,
+    pub status_code: Option<u16>,
+    pub error: Option<String>,
+    pub duration_ms: u64,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// Delivery of one event to one webhook
+#[derive(Debug, Clone, Serialize)]
+pub struct Delivery {
+    pub id: String,
+    pub webhook_id: String,
+    pub event: String,
+    pub status: DeliveryStatus,
+    pub attempts: Vec<DeliveryAttempt>,
+    pub next_attempt_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// An event delivery that was delivered or dead-lettered
+#[derive(Debug, Clone)]
+pub struct FinishedDelivery {
+    /// Owner of the webhook delivered to
+    pub owner: String,
+    pub delivery: Delivery,
+}
+
+/// Compute the `sha256=<hex>` signature of a payload
+pub fn sign(secret: &str, timestamp: i64, body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+        .expect("HMAC accepts keys of any length");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    format!("sha256={}", to_hex(&mac.finalize().into_bytes()))
+}
+
+/// Verify a signature produced by [`sign`] in constant time
+pub fn verify(secret: &str, timestamp: i64, body: &[u8], signature: &str) -> bool {
+    let Some(expected) = signature.strip_prefix("sha256=").and_then(from_hex) else {
+        return false;
+    };
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+        .expect("HMAC accepts keys of any length");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    mac.verify_slice(&expected).is_ok()
+}
+
+pub(crate) fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+pub(crate) fn from_hex(s: &str) -> Option<Vec<u8>> {
+    if !s.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&s[i..i + 2], 16).ok())
+        .collect()
+}
+
+fn storage_error(e: sqlx::Error) -> hal9_core::Error {
+    hal9_core::Error::Storage(format!("Webhooks: {}", e))
+}
+
+/// The `webhooks` table. Timestamps are Unix milliseconds; secrets are kept
+/// as given, since deliveries are signed with them.
+pub struct WebhookStore {
+    pools: SqlitePools,
+}
+
+impl WebhookStore {
+    pub fn new(pools: SqlitePools) -> Self {
+        Self { pools }
+    }
+
+    /// Create the webhooks table
+    pub async fn initialize(&self) -> hal9_core::Result<()> {
+        let statements = [
+            r#"
+            CREATE TABLE IF NOT EXISTS webhooks (
+                id TEXT PRIMARY KEY,
+                owner TEXT NOT NULL,
+                url TEXT NOT NULL,
+                events TEXT NOT NULL,
+                description TEXT,
+                secret TEXT NOT NULL,
+                created_at INTEGER NOT NULL
+            )
+            "#,
+            "CREATE INDEX IF NOT EXISTS idx_webhooks_owner ON webhooks(owner)",
+        ];
+        for statement in statements {
+            sqlx::query(statement).execute(self.pools.writer()).await.map_err(storage_error)?;
+        }
+        Ok(())
+    }
+
+    pub async fn insert(&self, webhook: &Webhook) -> hal9_core::Result<()> {
+        let events = serde_json::to_string(&webhook.events)?;
+        let (row, events) = (webhook, &events);
+        self.pools
+            .write(|pool| async move {
+                sqlx::query(
+                    "INSERT INTO webhooks (id, owner, url, events, description, secret, created_at) VALUES ($1, $2, $3, $4, $5, $6, $7)",
+                )
+                .bind(&row.id)
+                .bind(&row.owner)
+                .bind(&row.url)
+                .bind(events)
+                .bind(&row.description)
+                .bind(row.secret.expose())
+                .bind(row.created_at.timestamp_millis())
+                .execute(&pool)
+                .await
+            })
+            .await
+            .map_err(storage_error)?;
+        Ok(())
+    }
+
+    pub async fn delete(&self, id: &str) -> hal9_core::Result<()> {
+        self.pools
+            .write(|pool| async move { sqlx::query("DELETE FROM webhooks WHERE id = $1").bind(id).execute(&pool).await })
+            .await
+            .map_err(storage_error)?;
+        Ok(())
+    }
+
+    /// Every subscription, oldest first
+    pub async fn load(&self) -> hal9_core::Result<Vec<Webhook>> {
+        let rows = sqlx::query(
+            "SELECT id, owner, url, events, description, secret, created_at FROM webhooks ORDER BY created_at",
+        )
+        .fetch_all(self.pools.reader())
+        .await
+        .map_err(storage_error)?;
+
+        let mut webhooks = Vec::with_capacity(rows.len());
+        for row in rows {
+            webhooks.push(Webhook {
+                id: row.get("id"),
+                owner: row.get("owner"),
+                url: row.get("url"),
+                events: serde_json::from_str(&row.get::<String, _>("events"))?,
+                description: row.get("description"),
+                created_at: Utc.timestamp_millis_opt(row.get("created_at")).single().unwrap_or_default(),
+                secret: SecretString::new(row.get::<String, _>("secret")),
+            });
+        }
+        Ok(webhooks)
+    }
+}
+
+/// Registers webhooks and delivers events to them
+pub struct WebhookManager {
+    config: WebhookConfig,
+    client: reqwest::Client,
+    webhooks: DashMap<String, Webhook>,
+    /// Where subscriptions are kept, once attached
+    store: OnceLock<WebhookStore>,
+    deliveries: DashMap<String, Vec<Delivery>>,
+    budget_alerts: Mutex<HashMap<String, Instant>>,
+    finished_tx: broadcast::Sender<FinishedDelivery>,
+}
+
+impl WebhookManager {
+    /// Create a new webhook manager
+    pub fn new(config: WebhookConfig) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(config.timeout_secs))
+            .build()
+            .unwrap_or_default();
+        let (finished_tx, _) = broadcast::channel(256);
+
+        Self {
+            config,
+            client,
+            webhooks: DashMap::new(),
+            store: OnceLock::new(),
+            deliveries: DashMap::new(),
+            budget_alerts: Mutex::new(HashMap::new()),
+            finished_tx,
+        }
+    }
+
+    /// Subscribe to event deliveries as they are delivered or dead-lettered.
+    /// Test deliveries are not announced.
+    pub fn subscribe(&self) -> broadcast::Receiver<FinishedDelivery> {
+        self.finished_tx.subscribe()
+    }
+
+    /// Keep subscriptions in `pools` from now on, picking up those
+    /// registered there before
+    pub async fn attach_store(&self, pools: SqlitePools) -> hal9_core::Result<()> {
+        let store = WebhookStore::new(pools);
+        store.initialize().await?;
+        for webhook in store.load().await? {
+            self.webhooks.insert(webhook.id.clone(), webhook);
+        }
+        self.store
+            .set(store)
+            .map_err(|_| hal9_core::Error::Config("Webhook store is already attached".to_string()))
+    }
+
+    /// Register a webhook for `owner`
+    pub async fn register(&self, owner: &str, req: CreateWebhookRequest) -> ServerResult<CreatedWebhook> {
+        let url = url::Url::parse(&req.url)
+            .map_err(|e| ServerError::InvalidInput(format!("Invalid webhook URL: {}", e)))?;
+        if url.scheme() != "http" && url.scheme() != "https" {
+            return Err(ServerError::InvalidInput("Webhook URL must be http or https".to_string()));
+        }
+        if req.events.is_empty() {
+            return Err(ServerError::InvalidInput("At least one event is required".to_string()));
+        }
+
+        let count = self.webhooks.iter().filter(|w| w.owner == owner).count();
+        if count >= self.config.max_webhooks_per_org {
+            return Err(ServerError::LimitExceeded(format!(
+                "Webhook limit reached ({} of {})",
+                count, self.config.max_webhooks_per_org
+            )));
+        }
+
+        let secret = req.secret
+            .filter(|s| !s.is_empty())
+            .unwrap_or_else(|| to_hex(&rand::random::<[u8; 32]>()));
+
+        let mut events: Vec<WebhookEvent> = Vec::new();
+        for event in req.events {
+            if !events.contains(&event) {
+                events.push(event);
+            }
+        }
+
+        let webhook = Webhook {
+            id: uuid::Uuid::new_v4().to_string(),
+            owner: owner.to_string(),
+            url: url.to_string(),
+            events,
+            description: req.description,
+            created_at: Utc::now(),
+            secret: SecretString::new(secret.clone()),
+        };
+        if let Some(store) = self.store.get() {
+            store.insert(&webhook).await.map_err(|e| ServerError::Internal(e.to_string()))?;
+        }
+        self.webhooks.insert(webhook.id.clone(), webhook.clone());
+
+        Ok(CreatedWebhook { webhook, secret })
+    }
+
+    /// Webhooks owned by `owner`
+    pub fn list(&self, owner: &str) -> Vec<Webhook> {
+        let mut webhooks: Vec<Webhook> = self.webhooks.iter()
+            .filter(|w| w.owner == owner)
+            .map(|w| w.clone())
+            .collect();
+        webhooks.sort_by_key(|w| w.created_at);
+        webhooks
+    }
+
+    /// Get a webhook owned by `owner`
+    pub fn get(&self, owner: &str, id: &str) -> ServerResult<Webhook> {
+        self.webhooks.get(id)
+            .filter(|w| w.owner == owner)
+            .map(|w| w.clone())
+            .ok_or_else(|| ServerError::NotFound(format!("Webhook {} not found", id)))
+    }
+
+    /// Remove a webhook and its delivery log
+    pub async fn delete(&self, owner: &str, id: &str) -> ServerResult<()> {
+        self.get(owner, id)?;
+        if let Some(store) = self.store.get() {
+            store.delete(id).await.map_err(|e| ServerError::Internal(e.to_string()))?;
+        }
+        self.webhooks.remove(id);
+        self.deliveries.remove(id);
+        Ok(())
+    }
+
+    /// Recent deliveries of a webhook, newest first
+    pub fn deliveries(&self, owner: &str, id: &str) -> ServerResult<Vec<Delivery>> {
+        self.get(owner, id)?;
+        let mut deliveries = self.deliveries.get(id)
+            .map(|d| d.clone())
+            .unwrap_or_default();
+        deliveries.reverse();
+        Ok(deliveries)
+    }
+
+    /// Emit an event to matching webhooks. `owner` restricts delivery to one
+    /// organization's webhooks; `None` notifies every subscriber.
+    pub fn emit(self: &Arc<Self>, event: WebhookEvent, owner: Option<&str>, data: Value) {
+        if !self.config.enabled {
+            return;
+        }
+
+        let targets: Vec<Webhook> = self.webhooks.iter()
+            .filter(|w| w.events.contains(&event))
+            .filter(|w| owner.is_none_or(|owner| w.owner == owner))
+            .map(|w| w.clone())
+            .collect();
+
+        for webhook in targets {
+            let manager = self.clone();
+            let data = data.clone();
+            tokio::spawn(async move {
+                let delivery = manager.deliver(&webhook, event.as_str(), data, manager.config.max_attempts).await;
+                let _ = manager.finished_tx.send(FinishedDelivery { owner: webhook.owner, delivery });
+            });
+        }
+    }
+
+    /// Emit a finished chain to its owner's webhooks
+    pub fn emit_chain(self: &Arc<Self>, owner: &str, result: &ChainResult) {
+        let Some(event) = WebhookEvent::for_chain_status(result.status) else {
+            return;
+        };
+        let data = json!({
+            "chain": result,
+            "links": { "result": self.link(&format!("/api/v1/chains/{}", result.chain_id)) },
+        });
+        self.emit(event, Some(owner), data);
+    }
+
+    /// Emit a budget alert, at most once per hour for each alert kind
+    pub fn emit_budget_threshold(self: &Arc<Self>, message: &str) {
+        let kind = message.split_whitespace()
+            .find(|w| w.chars().all(|c| c.is_alphabetic()))
+            .unwrap_or("budget")
+            .to_string();
+        {
+            let mut alerts = self.budget_alerts.lock();
+            if alerts.get(&kind).is_some_and(|last| last.elapsed() < BUDGET_ALERT_COOLDOWN) {
+                return;
+            }
+            alerts.insert(kind, Instant::now());
+        }
+        self.emit(WebhookEvent::BudgetThreshold, None, json!({ "message": message }));
+    }
+
+    /// Emit an SLO burn-rate alert to every subscriber
+    pub fn emit_slo_alert(self: &Arc<Self>, alert: &SloAlert) {
+        let data = json!({
+            "alert": alert,
+            "links": { "status": self.link("/api/v1/slo/status") },
+        });
+        self.emit(WebhookEvent::SloBurnRate, None, data);
+    }
+
+    /// Emit a move of the Gentle Singularity stage to every subscriber
+    pub fn emit_singularity_stage(self: &Arc<Self>, transition: &StageTransition) {
+        self.emit(WebhookEvent::SingularityStageChanged, None, json!({ "transition": transition }));
+    }
+
+    /// Emit an output held for approval to the webhooks of its chain's owner
+    pub fn emit_approval(self: &Arc<Self>, owner: &str, approval: &PendingApproval) {
+        let data = json!({
+            "approval": approval,
+            "links": {
+                "approve": self.link(&format!("/api/v1/approvals/{}/approve", approval.id)),
+                "reject": self.link(&format!("/api/v1/approvals/{}/reject", approval.id)),
+            },
+        });
+        self.emit(WebhookEvent::ApprovalRequested, Some(owner), data);
+    }
+
+    /// Send a single test delivery to a webhook, without retries
+    pub async fn send_test(&self, owner: &str, id: &str) -> ServerResult<Delivery> {
+        let webhook = self.get(owner, id)?;
+        let data = json!({ "message": "Test delivery from HAL9", "webhook_id": webhook.id });
+        Ok(self.deliver(&webhook, TEST_EVENT, data, 1).await)
+    }
+
+    /// Deliver an event, retrying with exponential backoff up to `max_attempts`
+    async fn deliver(&self, webhook: &Webhook, event: &str, data: Value, max_attempts: u32) -> Delivery {
+        let mut delivery = Delivery {
+            id: uuid::Uuid::new_v4().to_string(),
+            webhook_id: webhook.id.clone(),
+            event: event.to_string(),
+            status: DeliveryStatus::Pending,
+            attempts: Vec::new(),
+            next_attempt_at: None,
+            created_at: Utc::now(),
+        };
+        let body = self.build_body(&delivery, data);
+        self.record(&delivery);
+
+        for attempt in 1..=max_attempts.max(1) {
+            let timestamp = Utc::now().timestamp();
+            let started = Instant::now();
+            let result = self.client.post(&webhook.url)
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .header(EVENT_HEADER, event)
+                .header(DELIVERY_HEADER, &delivery.id)
+                .header(TIMESTAMP_HEADER, timestamp.to_string())
+                .header(SIGNATURE_HEADER, sign(webhook.secret.expose(), timestamp, &body))
+                .body(body.clone())
+                .send()
+                .await;
+
+            let (status_code, error) = match result {
+                Ok(response) if response.status().is_success() => (Some(response.status().as_u16()), None),
+                Ok(response) => (Some(response.status().as_u16()), Some(format!("HTTP {}", response.status()))),
+                Err(e) => (None, Some(e.to_string())),
+            };
+            let failed = error.is_some();
+
+            delivery.attempts.push(DeliveryAttempt {
+                attempt,
+                status_code,
+                error,
+                duration_ms: started.elapsed().as_millis() as u64,
+                timestamp: Utc::now(),
+            });
+
+            if !failed {
+                delivery.status = DeliveryStatus::Delivered;
+                delivery.next_attempt_at = None;
+                self.record(&delivery);
+                debug!("Webhook {} delivered {} ({})", webhook.id, event, delivery.id);
+                return delivery;
+            }
+
+            if attempt >= max_attempts {
+                break;
+            }
+
+            let backoff = self.backoff(attempt);
+            delivery.status = DeliveryStatus::Retrying;
+            delivery.next_attempt_at = chrono::Duration::from_std(backoff).ok().map(|d| Utc::now() + d);
+            self.record(&delivery);
+            tokio::time::sleep(backoff).await;
+        }
+
+        warn!("Webhook {} delivery {} dead-lettered after {} attempts", webhook.id, delivery.id, delivery.attempts.len());
+        delivery.status = DeliveryStatus::DeadLettered;
+        delivery.next_attempt_at = None;
+        self.record(&delivery);
+        delivery
+    }
+
+    /// Delay before retrying after `attempt` failed attempts
+    fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 2u64.saturating_pow(attempt.saturating_sub(1));
+        Duration::from_millis(
+            self.config.initial_backoff_ms.saturating_mul(factor).min(self.config.max_backoff_ms),
+        )
+    }
+
+    /// Serialize the payload envelope, shrinking it under the size cap
+    fn build_body(&self, delivery: &Delivery, data: Value) -> Vec<u8> {
+        let envelope = |data: Value, truncated: bool| json!({
+            "id": delivery.id,
+            "event": delivery.event,
+            "created_at": delivery.created_at,
+            "truncated": truncated,
+            "data": data,
+        });
+
+        let full = serde_json::to_vec(&envelope(data.clone(), false)).unwrap_or_default();
+        if full.len() <= self.config.max_payload_bytes {
+            return full;
+        }
+
+        // Shorten long strings until the payload fits; links stay intact
+        let mut limit = 4096;
+        while limit >= 64 {
+            let mut shrunk = data.clone();
+            truncate_strings(&mut shrunk, limit);
+            let body = serde_json::to_vec(&envelope(shrunk, true)).unwrap_or_default();
+            if body.len() <= self.config.max_payload_bytes {
+                return body;
+            }
+            limit /= 2;
+        }
+
+        let links = data.get("links").cloned().unwrap_or(Value::Null);
+        serde_json::to_vec(&envelope(json!({ "links": links }), true)).unwrap_or_default()
+    }
+
+    fn record(&self, delivery: &Delivery) {
+        let mut log = self.deliveries.entry(delivery.webhook_id.clone()).or_default();
+        match log.iter_mut().find(|d| d.id == delivery.id) {
+            Some(existing) => *existing = delivery.clone(),
+            None => {
+                log.push(delivery.clone());
+                if log.len() > DELIVERY_HISTORY {
+                    log.remove(0);
+                }
+            }
+        }
+    }
+
+    fn link(&self, path: &str) -> String {
+        match &self.config.public_base_url {
+            Some(base) => format!("{}{}", base.trim_end_matches('/'), path),
+            None => path.to_string(),
+        }
+    }
+}
+
+/// Truncate every string in `value` (except links) to at most `limit` chars
+fn truncate_strings(value: &mut Value, limit: usize) {
+    match value {
+        Value::String(s) if s.chars().count() > limit => {
+            *s = format!("{}…", s.chars().take(limit).collect::<String>());
+        }
+        Value::Array(items) => items.iter_mut().for_each(|v| truncate_strings(v, limit)),
+        Value::Object(map) => {
+            for (key, v) in map.iter_mut() {
+                if key != "links" {
+                    truncate_strings(v, limit);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signature_round_trip() {
+        let signature = sign("secret", 1700000000, b"{\"a\":1}");
+        assert!(signature.starts_with("sha256="));
+        assert!(verify("secret", 1700000000, b"{\"a\":1}", &signature));
+        assert!(!verify("other", 1700000000, b"{\"a\":1}", &signature));
+        assert!(!verify("secret", 1700000001, b"{\"a\":1}", &signature));
+    }
+
+    #[tokio::test]
+    async fn test_registration_validates_and_caps() {
+        let config = WebhookConfig { max_webhooks_per_org: 1, ..Default::default() };
+        let manager = WebhookManager::new(config);
+        let req = |url: &str| CreateWebhookRequest {
+            url: url.to_string(),
+            secret: None,
+            events: vec![WebhookEvent::ChainCompleted],
+            description: None,
+        };
+
+        assert!(matches!(manager.register("org", req("ftp://x")).await, Err(ServerError::InvalidInput(_))));
+        let created = manager.register("org", req("https://example.com/hook")).await.unwrap();
+        assert_eq!(created.secret.len(), 64);
+        assert!(matches!(manager.register("org", req("https://example.com/2")).await, Err(ServerError::LimitExceeded(_))));
+        assert!(manager.register("other", req("https://example.com/3")).await.is_ok());
+        assert!(manager.get("other", &created.webhook.id).is_err());
+    }
+
+    #[test]
+    fn test_oversized_payload_is_truncated_keeping_links() {
+        let config = WebhookConfig { max_payload_bytes: 1024, ..Default::default() };
+        let manager = WebhookManager::new(config);
+        let delivery = Delivery {
+            id: "d".to_string(),
+            webhook_id: "w".to_string(),
+            event: "chain.completed".to_string(),
+            status: DeliveryStatus::Pending,
+            attempts: Vec::new(),
+            next_attempt_at: None,
+            created_at: Utc::now(),
+        };
+        let data = json!({
+            "chain": { "final_output": "x".repeat(10_000) },
+            "links": { "result": "/api/v1/chains/abc" },
+        });
+
+        let body = manager.build_body(&delivery, data);
+        assert!(body.len() <= 1024);
+        let parsed: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(parsed["truncated"], true);
+        assert_eq!(parsed["data"]["links"]["result"], "/api/v1/chains/abc");
+    }
+}
@@ -0,0 +1,18 @@
+//! Worker process running one isolated neuron for a 2HAL9 server
+//!
+//! Spawned by the server for neurons with `settings.isolation: "process"`;
+//! stdin and stdout carry the worker protocol, so logs go to stderr.
+
+use anyhow::Result;
+
+extern crate hal9_server;
+
+use hal9_server::{isolation, logging};
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> Result<()> {
+    logging::init_stderr_logging();
+    
+    isolation::run_worker(tokio::io::stdin(), tokio::io::stdout()).await?;
+    Ok(())
+}
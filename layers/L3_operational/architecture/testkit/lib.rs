@@ -0,0 +1,246 @@
+//! Deterministic simulation harness for HAL9 integration tests
+//!
+//! [`SimulationHarness`] boots an in-process [`HAL9Server`] in simulation
+//! mode: mock Claude, every random draw seeded from one seed, and any
+//! database the server opens kept in memory. Tests run on tokio's paused
+//! clock, so mock delays, retry backoff, circuit breaker timeouts and Claude
+//! deadlines pass in virtual time, instantly and in the same order on every
+//! run:
+//!
+//! ```no_run
+//! use std::time::Duration;
+//! use hal9_testkit::SimulationHarness;
+//!
+//! #[tokio::test(start_paused = true)]
+//! async fn chain_reaches_l2() {
+//!     let harness = SimulationHarness::builder().seed(7).start().await.unwrap();
+//!     let chain_id = harness.submit("L4", "Split the task").await;
+//!     let result = harness.run_chain(&chain_id, Duration::from_secs(60)).await;
+//!     assert_eq!(result.layers, ["L4", "L3", "L2"]);
+//!     assert_eq!(harness.signal_tree(&chain_id).await.nodes.len(), 3);
+//!     harness.shutdown().await;
+//! }
+//! ```
+//!
+//! The harness needs a current-thread runtime with paused time, which is
+//! what `#[tokio::test(start_paused = true)]` provides. While the runtime is
+//! idle tokio jumps the clock to the next timer, so awaiting a chain costs
+//! no wall-clock time.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde_json::json;
+use tokio::time::Instant;
+
+use hal9_core::config::MockResponse;
+use hal9_core::sqlite::{SqlitePools, SqliteTuning};
+use hal9_core::{NeuronConfig, NeuronSignal, Result, ServerConfig};
+use hal9_server::chain_tracker::{ChainResult, ChainStatus};
+use hal9_server::chain_visualization::ChainGraph;
+use hal9_server::metrics::MetricsSnapshot;
+use hal9_server::simulation::{FaultInjector, Simulation};
+use hal9_server::HAL9Server;
+
+/// Seed used unless the builder sets one
+pub const DEFAULT_SEED: u64 = 9;
+
+/// Mock Claude delay of the default neurons
+pub const DEFAULT_DELAY_MS: u64 = 100;
+
+/// How often [`SimulationHarness::run_chain`] checks on a chain, in virtual time
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Configures the server a harness boots
+pub struct HarnessBuilder {
+    config: ServerConfig,
+    dir: tempfile::TempDir,
+}
+
+impl HarnessBuilder {
+    /// Three neurons forwarding L4 → L3 → L2 (`sim-l4`, `sim-l3`, `sim-l2`),
+    /// each answering its layer's default after [`DEFAULT_DELAY_MS`]
+    fn new() -> Self {
+        let dir = tempfile::tempdir().expect("failed to create harness directory");
+        let config = json!({
+            "server_id": "simulation",
+            "neurons": [
+                {"id": "sim-l4", "layer": "L4", "forward_connections": ["sim-l3"], "backward_connections": []},
+                {"id": "sim-l3", "layer": "L3", "forward_connections": ["sim-l2"], "backward_connections": ["sim-l4"]},
+                {"id": "sim-l2", "layer": "L2", "forward_connections": [], "backward_connections": ["sim-l3"]},
+            ],
+            "claude": {
+                "mode": "mock",
+                "mock_responses": {
+                    "L4": [{"trigger": "default", "response": "FORWARD_TO: sim-l3\nCONTENT: Split the task into parts", "delay_ms": DEFAULT_DELAY_MS}],
+                    "L3": [{"trigger": "default", "response": "FORWARD_TO: sim-l2\nCONTENT: Specify the first part", "delay_ms": DEFAULT_DELAY_MS}],
+                    "L2": [{"trigger": "default", "response": "RESULT: First part done", "delay_ms": DEFAULT_DELAY_MS}],
+                },
+            },
+            "simulation": {"enabled": true, "seed": DEFAULT_SEED},
+            "layer_pause": {"state_path": dir.path().join("layer_pauses.json")},
+        });
+
+        Self {
+            config: serde_json::from_value(config).expect("invalid harness config"),
+            dir,
+        }
+    }
+
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.config.simulation.seed = seed;
+        self
+    }
+
+    /// Replace the default neurons
+    pub fn neurons(mut self, neurons: Vec<NeuronConfig>) -> Self {
+        self.config.neurons = neurons;
+        self
+    }
+
+    /// Answer every call on `layer` with `response` after `delay_ms`
+    pub fn mock_response(mut self, layer: &str, response: &str, delay_ms: u64) -> Self {
+        self.config.claude.mock_responses.insert(layer.to_string(), vec![MockResponse {
+            trigger: "default".to_string(),
+            response: response.to_string(),
+            delay_ms,
+        }]);
+        self
+    }
+
+    /// Fail calls on `layer` ("*" for all) with probability `rate`
+    pub fn fault_rate(mut self, layer: &str, rate: f64) -> Self {
+        self.config.simulation.fault_rates.insert(layer.to_string(), rate);
+        self
+    }
+
+    /// Keep neuron memory in an in-memory database
+    pub fn memory(mut self) -> Self {
+        self.config.memory.enabled = true;
+        self.config.memory.database_path = ":memory:".to_string();
+        self
+    }
+
+    /// Enable authentication backed by an in-memory database
+    pub fn auth(mut self) -> Self {
+        self.config.auth.enabled = true;
+        self
+    }
+
+    /// Any other change to the server config
+    pub fn configure(mut self, f: impl FnOnce(&mut ServerConfig)) -> Self {
+        f(&mut self.config);
+        self
+    }
+
+    /// Boot the server
+    pub async fn start(self) -> Result<SimulationHarness> {
+        let mut server = HAL9Server::new(self.config.clone());
+        if self.config.auth.enabled {
+            let pools = SqlitePools::connect("sqlite::memory:", SqliteTuning::default()).await?;
+            server.initialize_auth(pools).await?;
+        }
+
+        let server = Arc::new(server);
+        server.start().await?;
+
+        Ok(SimulationHarness {
+            server,
+            neurons: self.config.neurons,
+            started_at: Instant::now(),
+            _dir: self.dir,
+        })
+    }
+}
+
+/// A server running in simulation mode on paused time
+pub struct SimulationHarness {
+    server: Arc<HAL9Server>,
+    neurons: Vec<NeuronConfig>,
+    started_at: Instant,
+    _dir: tempfile::TempDir,
+}
+
+impl SimulationHarness {
+    pub fn builder() -> HarnessBuilder {
+        HarnessBuilder::new()
+    }
+
+    pub fn server(&self) -> &Arc<HAL9Server> {
+        &self.server
+    }
+
+    pub fn simulation(&self) -> &Simulation {
+        self.server.simulation()
+    }
+
+    /// Scripted and random Claude failures
+    pub fn faults(&self) -> &Arc<FaultInjector> {
+        self.simulation().faults()
+    }
+
+    /// Virtual time since the server started
+    pub fn elapsed(&self) -> Duration {
+        self.started_at.elapsed()
+    }
+
+    /// Let `by` of virtual time pass, firing every timer due on the way in
+    /// order
+    pub async fn advance(&self, by: Duration) {
+        tokio::time::sleep(by).await;
+    }
+
+    /// Submit `content` to the first neuron of `layer` as a new chain,
+    /// returning the chain ID
+    pub async fn submit(&self, layer: &str, content: &str) -> String {
+        let neuron = self.neurons.iter()
+            .find(|neuron| neuron.layer == layer)
+            .map(|neuron| neuron.id.clone())
+            .unwrap_or_else(|| panic!("no neuron on layer {}", layer));
+        let signal = NeuronSignal::forward("simulation", &neuron, "client", layer, content.to_string());
+
+        self.server.submit_signal(signal).await
+            .unwrap_or_else(|e| panic!("failed to submit to {}: {}", neuron, e))
+    }
+
+    /// Wait until a chain finishes, failing the test if it is still running
+    /// after `limit` of virtual time
+    pub async fn run_chain(&self, chain_id: &str, limit: Duration) -> ChainResult {
+        let deadline = Instant::now() + limit;
+        loop {
+            let result = self.chain_result(chain_id).await;
+            if result.status != ChainStatus::Running {
+                return result;
+            }
+            assert!(
+                Instant::now() < deadline,
+                "chain {} still running after {:?}: {:?}", chain_id, limit, result,
+            );
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    }
+
+    pub async fn chain_result(&self, chain_id: &str) -> ChainResult {
+        self.server.get_chain_result(chain_id).await
+            .unwrap_or_else(|e| panic!("unknown chain {}: {}", chain_id, e))
+    }
+
+    /// Signal tree of a chain
+    pub async fn signal_tree(&self, chain_id: &str) -> ChainGraph {
+        self.server.chain_graph(chain_id).await
+            .unwrap_or_else(|e| panic!("unknown chain {}: {}", chain_id, e))
+    }
+
+    pub fn metrics(&self) -> MetricsSnapshot {
+        self.server.metrics().snapshot()
+    }
+
+    /// Times `error_type` was recorded in the metrics
+    pub fn errors(&self, error_type: &str) -> u64 {
+        self.metrics().errors_by_type.get(error_type).copied().unwrap_or(0)
+    }
+
+    pub async fn shutdown(self) {
+        self.server.shutdown().await.expect("failed to shut down server");
+    }
+}
@@ -0,0 +1,290 @@
+//! Full-chain tests on virtual time
+//!
+//! Every test runs on a paused clock with a seeded simulation, so timing
+//! assertions are exact and repeated runs see the same outcomes. Signal
+//! propagation, metrics and circuit breaker tests moved here from the
+//! server's integration tests, which polled on wall-clock sleeps.
+
+use std::time::Duration;
+
+use hal9_core::consciousness::FlowDirection;
+use hal9_core::NeuronSignal;
+use hal9_server::chain_tracker::ChainStatus;
+use hal9_server::circuit_breaker::CircuitBreakerConfig;
+use hal9_server::error::ServerError;
+use hal9_server::error_recovery::RetryMiddleware;
+use hal9_server::output_format::{request_format, OutputFormat};
+use hal9_server::simulation::{SimRng, INJECTED_FAULT};
+use hal9_testkit::{SimulationHarness, DEFAULT_DELAY_MS, DEFAULT_SEED};
+use rand::Rng;
+
+const CHAIN_LIMIT: Duration = Duration::from_secs(120);
+
+/// Chains of a run: errors and final output of each, with the virtual time
+/// and faults the run took
+async fn run_scenario(seed: u64) -> (Vec<(ChainStatus, Vec<String>, Option<String>)>, Duration, u64) {
+    let harness = SimulationHarness::builder()
+        .seed(seed)
+        .fault_rate("*", 0.3)
+        .start()
+        .await
+        .unwrap();
+
+    let mut outcomes = Vec::new();
+    for i in 0..10 {
+        let chain_id = harness.submit("L4", &format!("Task {}", i)).await;
+        let result = harness.run_chain(&chain_id, CHAIN_LIMIT).await;
+        outcomes.push((result.status, result.errors, result.final_output));
+    }
+
+    let elapsed = harness.elapsed();
+    let injected = harness.faults().injected();
+    harness.shutdown().await;
+    (outcomes, elapsed, injected)
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_chain_builds_signal_tree() {
+    let harness = SimulationHarness::builder().start().await.unwrap();
+
+    let chain_id = harness.submit("L4", "Split the work").await;
+    let result = harness.run_chain(&chain_id, CHAIN_LIMIT).await;
+
+    assert_eq!(result.status, ChainStatus::Completed);
+    assert!(result.errors.is_empty(), "{:?}", result.errors);
+    assert_eq!(result.final_output.as_deref(), Some("RESULT: First part done"));
+
+    let tree = harness.signal_tree(&chain_id).await;
+    let layers: Vec<_> = tree.nodes.iter().map(|n| (n.layer.as_str(), n.depth)).collect();
+    assert_eq!(layers, [("L4", 0), ("L3", 1), ("L2", 2)]);
+    assert_eq!(tree.edges.len(), 2);
+
+    // Three mock calls of 100ms plus up to 20% variance each
+    let elapsed = harness.elapsed();
+    assert!(elapsed >= Duration::from_millis(3 * DEFAULT_DELAY_MS), "{:?}", elapsed);
+    assert!(elapsed < Duration::from_millis(3 * DEFAULT_DELAY_MS * 6 / 5 + 50), "{:?}", elapsed);
+
+    harness.shutdown().await;
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_chain_traffic_feeds_layer_compression() {
+    let harness = SimulationHarness::builder().start().await.unwrap();
+
+    let chain_id = harness.submit("L4", "Split the work").await;
+    harness.run_chain(&chain_id, CHAIN_LIMIT).await;
+
+    let compression = harness.metrics().layer_compression;
+    let boundaries: Vec<_> = compression.iter()
+        .map(|flow| (flow.boundary(), flow.direction))
+        .collect();
+    assert_eq!(boundaries, [
+        ("L4-L3".to_string(), FlowDirection::Downward),
+        ("L3-L2".to_string(), FlowDirection::Downward),
+    ]);
+    for flow in &compression {
+        assert_eq!(flow.ratio, flow.entering as f64 / flow.forwarded as f64);
+    }
+
+    harness.shutdown().await;
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_chain_output_in_requested_format() {
+    let harness = SimulationHarness::builder().start().await.unwrap();
+
+    let mut signal = NeuronSignal::forward("simulation", "sim-l4", "client", "L4", "Split the work".to_string());
+    request_format(&mut signal.metadata, OutputFormat::Text, None);
+    let chain_id = harness.server().submit_signal(signal).await.unwrap();
+    let result = harness.run_chain(&chain_id, CHAIN_LIMIT).await;
+
+    assert_eq!(result.output_format, Some(OutputFormat::Text));
+    assert!(!result.format_degraded);
+    assert_eq!(result.final_output.as_deref(), Some("RESULT: First part done"));
+
+    harness.shutdown().await;
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_chain_output_degrades_when_reformat_fails() {
+    // The mock reformatter answers like L2 does, which is never JSON
+    let harness = SimulationHarness::builder().start().await.unwrap();
+
+    let mut signal = NeuronSignal::forward("simulation", "sim-l4", "client", "L4", "Split the work".to_string());
+    request_format(&mut signal.metadata, OutputFormat::Json, None);
+    let chain_id = harness.server().submit_signal(signal).await.unwrap();
+    let result = harness.run_chain(&chain_id, CHAIN_LIMIT).await;
+
+    assert_eq!(result.status, ChainStatus::Completed);
+    assert_eq!(result.output_format, Some(OutputFormat::Json));
+    assert!(result.format_degraded);
+    assert!(result.format_reason.unwrap().contains("not valid JSON"));
+    assert_eq!(result.final_output.as_deref(), Some("RESULT: First part done"));
+    assert_eq!(harness.errors("format_degraded"), 1);
+
+    harness.shutdown().await;
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_same_seed_replays_identically() {
+    let (first, first_elapsed, first_injected) = run_scenario(DEFAULT_SEED).await;
+    let (second, second_elapsed, second_injected) = run_scenario(DEFAULT_SEED).await;
+
+    assert_eq!(first, second);
+    assert_eq!(first_elapsed, second_elapsed);
+    assert_eq!(first_injected, second_injected);
+
+    // An injected fault ends its chain, so each one shows up exactly once
+    let failed = first.iter().filter(|(_, errors, _)| !errors.is_empty()).count();
+    assert_eq!(failed as u64, first_injected);
+    for (_, errors, _) in &first {
+        assert!(errors.iter().all(|e| e.contains(INJECTED_FAULT)), "{:?}", errors);
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_metrics_collection() {
+    let harness = SimulationHarness::builder().start().await.unwrap();
+
+    for i in 0..5 {
+        let chain_id = harness.submit("L4", &format!("test signal {}", i)).await;
+        harness.run_chain(&chain_id, CHAIN_LIMIT).await;
+    }
+
+    let metrics = harness.metrics();
+    assert_eq!(metrics.signals_sent, 5);
+    assert_eq!(metrics.signals_processed, 15);
+    assert_eq!(metrics.signals_failed, 0);
+    assert!(!metrics.processing_times.is_empty());
+
+    harness.shutdown().await;
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_circuit_breaker_opens_and_half_opens() {
+    let harness = SimulationHarness::builder().start().await.unwrap();
+    let breaker = CircuitBreakerConfig::default();
+
+    // Enough consecutive failures on L2 open its breaker
+    harness.faults().fail_next("sim-l2", breaker.failure_threshold);
+    for i in 0..breaker.failure_threshold {
+        let chain_id = harness.submit("L4", &format!("Task {}", i)).await;
+        let result = harness.run_chain(&chain_id, CHAIN_LIMIT).await;
+        assert_eq!(result.errors.len(), 1, "{:?}", result.errors);
+        assert!(result.errors[0].contains(INJECTED_FAULT), "{:?}", result.errors);
+    }
+
+    // Open: rejected without calling Claude
+    let chain_id = harness.submit("L4", "Rejected").await;
+    let result = harness.run_chain(&chain_id, CHAIN_LIMIT).await;
+    assert!(result.final_output.as_deref() != Some("RESULT: First part done"));
+    assert_eq!(harness.errors("circuit_breaker_open"), 1);
+
+    // Still open short of the timeout
+    harness.advance(breaker.timeout / 2).await;
+    let chain_id = harness.submit("L4", "Still rejected").await;
+    harness.run_chain(&chain_id, CHAIN_LIMIT).await;
+    assert_eq!(harness.errors("circuit_breaker_open"), 2);
+
+    // Half-open after the timeout lets a request through, and it succeeds
+    harness.advance(breaker.timeout).await;
+    let chain_id = harness.submit("L4", "Recovered").await;
+    let result = harness.run_chain(&chain_id, CHAIN_LIMIT).await;
+    assert!(result.errors.is_empty(), "{:?}", result.errors);
+    assert_eq!(result.final_output.as_deref(), Some("RESULT: First part done"));
+    assert_eq!(harness.errors("circuit_breaker_open"), 2);
+    assert_eq!(harness.faults().injected(), breaker.failure_threshold as u64);
+
+    harness.shutdown().await;
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_circuit_breaker_failure_in_half_open_reopens() {
+    let harness = SimulationHarness::builder().start().await.unwrap();
+    let breaker = CircuitBreakerConfig::default();
+
+    harness.faults().fail_next("sim-l2", breaker.failure_threshold + 1);
+    for i in 0..breaker.failure_threshold {
+        let chain_id = harness.submit("L4", &format!("Task {}", i)).await;
+        harness.run_chain(&chain_id, CHAIN_LIMIT).await;
+    }
+
+    // The trial request fails, reopening the breaker at once
+    harness.advance(breaker.timeout).await;
+    let chain_id = harness.submit("L4", "Trial").await;
+    let result = harness.run_chain(&chain_id, CHAIN_LIMIT).await;
+    assert!(result.errors.iter().any(|e| e.contains(INJECTED_FAULT)), "{:?}", result.errors);
+    assert_eq!(harness.errors("circuit_breaker_open"), 0);
+
+    let chain_id = harness.submit("L4", "Rejected").await;
+    harness.run_chain(&chain_id, CHAIN_LIMIT).await;
+    assert_eq!(harness.errors("circuit_breaker_open"), 1);
+    assert_eq!(harness.faults().injected(), breaker.failure_threshold as u64 + 1);
+
+    harness.shutdown().await;
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_chain_deadline() {
+    // L2 answers long after the 30s Claude deadline
+    let harness = SimulationHarness::builder()
+        .mock_response("L2", "RESULT: Too late", 45_000)
+        .start()
+        .await
+        .unwrap();
+
+    let chain_id = harness.submit("L4", "Split the work").await;
+    let result = harness.run_chain(&chain_id, CHAIN_LIMIT).await;
+
+    assert_eq!(result.errors.len(), 1, "{:?}", result.errors);
+    assert!(result.errors[0].contains("timeout"), "{:?}", result.errors);
+    assert_eq!(harness.errors("timeout"), 1);
+
+    // L4 and L3 take 100-120ms each before L2 times out
+    let elapsed = harness.elapsed();
+    assert!(elapsed >= Duration::from_millis(30_200), "{:?}", elapsed);
+    assert!(elapsed < Duration::from_millis(30_300), "{:?}", elapsed);
+
+    harness.shutdown().await;
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_retry_backoff_timing() {
+    let harness = SimulationHarness::builder().start().await.unwrap();
+
+    // The middleware and the expectation draw the same seeded jitter
+    let retry = RetryMiddleware::new(3).with_rng(harness.simulation().rng("retry"));
+    let mut jitter = SimRng::seeded(DEFAULT_SEED).fork("retry");
+    let expected: Duration = [100.0, 200.0]
+        .iter()
+        .map(|base: &f64| Duration::from_millis((base * (1.0 + jitter.gen::<f64>() * 0.3)) as u64))
+        .sum();
+
+    let started = tokio::time::Instant::now();
+    let mut attempts = 0;
+    let result = retry.execute(|| {
+        attempts += 1;
+        if attempts < 3 {
+            Err(ServerError::Internal("Temporary failure".to_string()))
+        } else {
+            Ok("Success")
+        }
+    }).await;
+
+    assert_eq!(result.unwrap(), "Success");
+    assert_eq!(attempts, 3);
+    let elapsed = started.elapsed();
+    assert!(elapsed >= expected && elapsed < expected + Duration::from_millis(5), "{:?} vs {:?}", elapsed, expected);
+
+    harness.shutdown().await;
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_simulation_requires_mock_claude() {
+    let result = SimulationHarness::builder()
+        .configure(|config| config.claude.mode = "hybrid".to_string())
+        .start()
+        .await;
+
+    assert!(result.is_err());
+}
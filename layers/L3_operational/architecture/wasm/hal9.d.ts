@@ -0,0 +1,333 @@
+// Generated from the Rust types by hal9-core-wasm; do not edit.
+
+/**
+ * A signal passed between neurons
+ */
+export type NeuronSignal = { 
+/**
+ * Format the signal is written in, see [`crate::signal_schema`].
+ * Signals read from any supported version are held at the current one.
+ */
+schema_version: number, signal_id: string, from_neuron: string, to_neuron: string, layer_from: string, layer_to: string, propagation_type: PropagationType, batch_id: string, timestamp: string, payload: SignalPayload, 
+/**
+ * Additional metadata for distributed routing. Keys are namespaced as
+ * registered in [`crate::metadata_schema`]; legacy keys are renamed on read.
+ */
+metadata: { [key in string]?: string }, };
+
+/**
+ * Direction of signal propagation
+ */
+export type PropagationType = "Forward" | "Backward";
+
+/**
+ * Signal payload containing activation and optional gradient
+ */
+export type SignalPayload = { activation: Activation, gradient: Gradient | null, };
+
+/**
+ * Forward activation data
+ */
+export type Activation = { content: string, strength: number, features: { [key in string]?: number }, };
+
+/**
+ * Backward gradient/error data
+ */
+export type Gradient = { error_type: string, magnitude: number, adjustments: Array<string>, loss: number, };
+
+/**
+ * Aggregated view of a chain's results
+ */
+export type ChainResult = { chain_id: string, status: ChainStatus, input: string, steps_completed: number, pending_signals: number, layers: Array<string>, 
+/**
+ * Output of the deepest layer a forward signal reached, if any step
+ * succeeded
+ */
+final_output: string | null, 
+/**
+ * Format requested for the final output
+ */
+output_format?: OutputFormat, 
+/**
+ * The final output did not conform to the requested format, even after
+ * reformatting, and is returned as it was
+ */
+format_degraded: boolean, 
+/**
+ * Why the final output failed its format check, when it did
+ */
+format_reason?: string, errors: Array<string>, 
+/**
+ * Backward (gradient) signals processed within the chain
+ */
+backward_steps: number, 
+/**
+ * The gradients those signals carried, in the order they were processed
+ */
+feedback?: Array<FeedbackEdge>, 
+/**
+ * Chain this one replays; compare the two with
+ * `/api/v1/chains/compare?a={replay_of}&b={chain_id}`
+ */
+replay_of?: string, 
+/**
+ * Cost attribution tags the chain was submitted with
+ */
+tags?: { [key in string]?: string }, 
+/**
+ * Node budget consumption and dropped branches, when a fan-out limit
+ * applied
+ */
+fan_out?: FanOutUsage, 
+/**
+ * Tool calls against the chain's sandbox limits, once the chain made
+ * one
+ */
+tools?: ToolUsage, 
+/**
+ * Memory entries each step's output cited, in the order the steps
+ * were processed; steps that cited none are left out
+ */
+cited_memories?: Array<NodeCitations>, 
+/**
+ * Branches parked while every provider was down, in the order they
+ * were parked; the chain keeps running until they resume
+ */
+deferred?: Array<DeferredBranch>, 
+/**
+ * Outputs held for approval, in the order they were held; the chain
+ * keeps running while one is pending
+ */
+gates?: Array<ApprovalGate>, prompt_tokens: number, completion_tokens: number, created_at: string, completed_at: string | null, duration_ms: number | null, 
+/**
+ * The chain's payloads were moved to the archive; served from its
+ * summary or read back from the archive files
+ */
+archived: boolean, };
+
+/**
+ * Chain lifecycle status
+ */
+export type ChainStatus = "running" | "completed" | "failed" | "cancelled";
+
+/**
+ * Shape of a chain's final output
+ */
+export type OutputFormat = "markdown" | "json" | "text";
+
+/**
+ * A backward signal carrying an error from one neuron back to another
+ */
+export type FeedbackEdge = { from_neuron: string, to_neuron: string, 
+/**
+ * Kind of error reported, e.g. `timeout`
+ */
+error_class: string, magnitude: number, 
+/**
+ * Factor the magnitude was scaled by when the signal was sent
+ */
+attenuation?: number, };
+
+/**
+ * Fan-out of a chain against its limits
+ */
+export type FanOutUsage = { 
+/**
+ * Signals the chain may process, its root included
+ */
+node_budget?: number, 
+/**
+ * Forward signals processed so far
+ */
+nodes: number, 
+/**
+ * Branches dropped across the chain
+ */
+truncated_branches: number, 
+/**
+ * Responses that asked for more branches than allowed
+ */
+truncations?: Array<FanOutTruncation>, };
+
+/**
+ * A response that asked for more branches than its limits allowed
+ */
+export type FanOutTruncation = { 
+/**
+ * Signal the response answered
+ */
+signal_id: string, neuron_id: string, layer: string, 
+/**
+ * Branches the response forwarded to
+ */
+requested: number, 
+/**
+ * Branches the limit allowed
+ */
+allowed: number, reason: TruncationReason, 
+/**
+ * In strict mode the chain failed instead and no branch was kept
+ */
+failed: boolean, };
+
+/**
+ * Why branches of a response were dropped
+ */
+export type TruncationReason = "max_children" | "node_budget";
+
+/**
+ * Tool calls of a chain against its sandbox limits, counted across all its
+ * branches
+ */
+export type ToolUsage = { 
+/**
+ * Tool calls made
+ */
+invocations: number, 
+/**
+ * Bytes of parameters sent to tools
+ */
+outbound_bytes: number, 
+/**
+ * Distinct domains of the URLs tools were called with, sorted
+ */
+domains?: Array<string>, 
+/**
+ * Milliseconds spent waiting on tool calls
+ */
+tool_time_ms: number, max_invocations?: number, max_outbound_bytes?: number, max_domains?: number, max_tool_time_ms?: number, 
+/**
+ * Tool calls refused for a limit, in the order they were refused
+ */
+violations?: Array<ToolViolation>, };
+
+/**
+ * A tool call refused, or cut short, for a limit of its chain
+ */
+export type ToolViolation = { 
+/**
+ * Signal whose processing made the call
+ */
+signal_id: string, neuron_id: string, tool: string, limit: ToolLimit, at: string, };
+
+/**
+ * A limit on the tool calls of a chain
+ */
+export type ToolLimit = "invocations" | "outbound_bytes" | "domains" | "tool_time";
+
+/**
+ * Memory entries the output of one step of a chain cited
+ */
+export type NodeCitations = { signal_id: string, neuron_id: string, layer: string, memories: Array<CitedMemory>, };
+
+/**
+ * A memory entry a neuron's output cited
+ */
+export type CitedMemory = { 
+/**
+ * Reference id the entry was given in the prompt, e.g. `mem-1a2b3c4d`
+ */
+reference: string, memory_id: string, 
+/**
+ * Namespace the entry belongs to
+ */
+namespace: string, };
+
+/**
+ * A branch of a chain parked during a provider outage
+ */
+export type DeferredBranch = { 
+/**
+ * Signal that was parked
+ */
+signal_id: string, neuron_id: string, layer: string, 
+/**
+ * Why it was parked, e.g. `deferred: provider outage`
+ */
+reason: string, deferred_at: string, 
+/**
+ * When it was processed after providers recovered; `None` while parked
+ */
+resumed_at?: string, };
+
+/**
+ * A step of a chain whose output was held for a human to approve
+ */
+export type ApprovalGate = { 
+/**
+ * Approval the gate is decided through, see `GET /api/v1/approvals`
+ */
+approval_id: string, 
+/**
+ * Signal whose output was held
+ */
+signal_id: string, 
+/**
+ * Signal that produced it; `None` for the chain root
+ */
+parent_id?: string, neuron_id: string, layer: string, 
+/**
+ * Why the output needs approval
+ */
+reason: string, status: GateStatus, 
+/**
+ * Who decided; `timeout` when the configured timeout action was taken
+ */
+decided_by?: string, 
+/**
+ * The approver amended the output before it continued
+ */
+edited: boolean, requested_at: string, decided_at?: string, };
+
+/**
+ * Where an approval gate stands
+ */
+export type GateStatus = "pending" | "approved" | "rejected";
+
+/**
+ * Error response format
+ */
+export type ErrorResponse = { error: ErrorDetails, retry_after?: number, help_url?: string, };
+
+export type ErrorDetails = { code: string, message: string, error_id?: string, details?: JsonValue, };
+
+export type JsonValue = number | string | boolean | Array<JsonValue> | { [key in string]?: JsonValue } | null;
+
+/**
+ * Schema as served to clients
+ */
+export type SchemaDescription = { mode: ValidationMode, namespaces: Array<NamespaceDescription>, };
+
+export type NamespaceDescription = { name: string, owner: string, keys: Array<KeySpec>, };
+
+/**
+ * A registered metadata key
+ */
+export type KeySpec = { 
+/**
+ * Full key including its namespace, e.g. `trace.chain_id`
+ */
+key: string, value_type: MetadataType, 
+/**
+ * Component responsible for writing the key
+ */
+owner: string, description: string, 
+/**
+ * Ad-hoc key used for the same value before namespacing
+ */
+legacy_key?: string, };
+
+/**
+ * Type a metadata value must parse as
+ */
+export type MetadataType = "string" | "integer" | "float" | "bool" | "uuid" | "timestamp";
+
+/**
+ * Why a metadata entry failed validation
+ */
+export type MetadataViolation = { "kind": "unknown_namespace", key: string, } | { "kind": "unknown_key", key: string, } | { "kind": "invalid_value", key: string, expected: MetadataType, value: string, };
+
+/**
+ * How keys outside the schema are treated
+ */
+export type ValidationMode = "strict" | "permissive";
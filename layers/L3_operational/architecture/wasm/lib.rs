@@ -0,0 +1,217 @@
+//! HAL9 data model for browser clients
+//!
+//! Compiles the signal, chain result, error and metadata types of
+//! `hal9-core` and `hal9-api-types` to WebAssembly, so the web dashboard
+//! builds and reads them with the server's own serde code instead of a
+//! TypeScript copy that drifts. Values cross the boundary as plain
+//! JavaScript objects typed by `hal9.d.ts`, which is generated from the Rust
+//! types and included in the package's declarations.
+//!
+//! Build the npm package, into `pkg/`, with
+//!
+//! ```sh
+//! wasm-pack build layers/L3_operational/architecture/wasm --release --scope hal9
+//! ```
+//!
+//! and regenerate `hal9.d.ts` after changing one of the types with
+//!
+//! ```sh
+//! UPDATE_SNAPSHOTS=1 cargo test -p hal9-core-wasm --features ts --test package_tests
+//! ```
+//!
+//! hal9-core is built without its `runtime` feature, which keeps tokio, sqlx
+//! and the rest of the server out of the module. The optimized module must
+//! stay under [`WASM_SIZE_BUDGET`]; `tests/package_tests.rs` checks the one
+//! in `pkg/` once it is built.
+
+use std::collections::HashMap;
+
+use serde::{de::DeserializeOwned, Serialize};
+use wasm_bindgen::prelude::*;
+
+use hal9_api_types::{ChainResult, ErrorCode, ErrorResponse};
+use hal9_core::config_layers::LayeredConfig;
+use hal9_core::metadata_schema::{MetadataSchema, ValidationMode};
+use hal9_core::{signal_schema, Gradient, NeuronSignal};
+
+/// Largest size, in bytes, of the module `wasm-pack build --release` writes
+pub const WASM_SIZE_BUDGET: u64 = 1024 * 1024;
+
+#[wasm_bindgen(typescript_custom_section)]
+const TYPES: &str = include_str!("hal9.d.ts");
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_namespace = JSON, js_name = parse, catch)]
+    fn json_parse(text: &str) -> Result<JsValue, JsValue>;
+
+    #[wasm_bindgen(js_namespace = JSON, js_name = stringify, catch)]
+    fn json_stringify(value: &JsValue) -> Result<Option<String>, JsValue>;
+}
+
+fn from_js<T: DeserializeOwned>(value: &JsValue) -> Result<T, JsError> {
+    let text = json_stringify(value)
+        .ok()
+        .flatten()
+        .ok_or_else(|| JsError::new("Value cannot be written as JSON"))?;
+    Ok(serde_json::from_str(&text)?)
+}
+
+fn to_js<T: Serialize>(value: &T) -> Result<JsValue, JsError> {
+    let text = serde_json::to_string(value)?;
+    json_parse(&text).map_err(|_| JsError::new("Value cannot be read back from JSON"))
+}
+
+/// New forward signal carrying `content`
+#[wasm_bindgen(js_name = forwardSignal, unchecked_return_type = "NeuronSignal")]
+pub fn forward_signal(from: &str, to: &str, layer_from: &str, layer_to: &str, content: String) -> Result<JsValue, JsError> {
+    to_js(&NeuronSignal::forward(from, to, layer_from, layer_to, content))
+}
+
+/// New backward signal carrying `gradient`
+#[wasm_bindgen(js_name = backwardSignal, unchecked_return_type = "NeuronSignal")]
+pub fn backward_signal(
+    from: &str,
+    to: &str,
+    layer_from: &str,
+    layer_to: &str,
+    #[wasm_bindgen(unchecked_param_type = "Gradient")] gradient: JsValue,
+) -> Result<JsValue, JsError> {
+    let gradient: Gradient = from_js(&gradient)?;
+    to_js(&NeuronSignal::backward(from, to, layer_from, layer_to, gradient))
+}
+
+/// Read a signal in any schema version the server reads, returning it in
+/// the current one with legacy metadata keys renamed
+#[wasm_bindgen(js_name = parseSignal, unchecked_return_type = "NeuronSignal")]
+pub fn parse_signal(#[wasm_bindgen(unchecked_param_type = "unknown")] value: JsValue) -> Result<JsValue, JsError> {
+    to_js(&from_js::<NeuronSignal>(&value)?)
+}
+
+/// Write a signal in an older schema version, for peers that read no newer
+#[wasm_bindgen(js_name = downgradeSignal, unchecked_return_type = "NeuronSignal")]
+pub fn downgrade_signal(
+    #[wasm_bindgen(unchecked_param_type = "NeuronSignal")] signal: JsValue,
+    version: u16,
+) -> Result<JsValue, JsError> {
+    let signal: NeuronSignal = from_js(&signal)?;
+    to_js(&signal_schema::downgrade(&signal, version)?)
+}
+
+/// Read a chain result as `GET /api/v1/chains/:id` returns it
+#[wasm_bindgen(js_name = parseChainResult, unchecked_return_type = "ChainResult")]
+pub fn parse_chain_result(#[wasm_bindgen(unchecked_param_type = "unknown")] value: JsValue) -> Result<JsValue, JsError> {
+    to_js(&from_js::<ChainResult>(&value)?)
+}
+
+/// Read the body of an error response
+#[wasm_bindgen(js_name = parseErrorResponse, unchecked_return_type = "ErrorResponse")]
+pub fn parse_error_response(#[wasm_bindgen(unchecked_param_type = "unknown")] value: JsValue) -> Result<JsValue, JsError> {
+    to_js(&from_js::<ErrorResponse>(&value)?)
+}
+
+/// Whether a request failing with error `code` may succeed if sent again
+/// later
+#[wasm_bindgen(js_name = isRetryable)]
+pub fn is_retryable(code: &str) -> bool {
+    ErrorCode::from(code).is_retryable()
+}
+
+/// Error code of a response with HTTP `status` that carries none of its own
+#[wasm_bindgen(js_name = errorCodeForStatus)]
+pub fn error_code_for_status(status: u16) -> String {
+    ErrorCode::from_status(status).as_str().to_string()
+}
+
+/// Namespaces and keys of the builtin metadata schema
+#[wasm_bindgen(js_name = metadataSchema, unchecked_return_type = "SchemaDescription")]
+pub fn metadata_schema() -> Result<JsValue, JsError> {
+    to_js(&MetadataSchema::builtin(ValidationMode::default()).describe())
+}
+
+/// Every violation in `metadata`, whatever the validation mode
+#[wasm_bindgen(js_name = checkMetadata, unchecked_return_type = "Array<MetadataViolation>")]
+pub fn check_metadata(
+    #[wasm_bindgen(unchecked_param_type = "Record<string, string>")] metadata: JsValue,
+) -> Result<JsValue, JsError> {
+    let metadata: HashMap<String, String> = from_js(&metadata)?;
+    to_js(&MetadataSchema::builtin(ValidationMode::default()).check(&metadata))
+}
+
+/// Throw the error a submission carrying `metadata` would get from a server
+/// validating in `mode`
+#[wasm_bindgen(js_name = validateMetadata)]
+pub fn validate_metadata(
+    #[wasm_bindgen(unchecked_param_type = "Record<string, string>")] metadata: JsValue,
+    #[wasm_bindgen(unchecked_param_type = "ValidationMode")] mode: JsValue,
+) -> Result<(), JsError> {
+    let metadata: HashMap<String, String> = from_js(&metadata)?;
+    let mode: ValidationMode = from_js(&mode)?;
+    Ok(MetadataSchema::builtin(mode).validate(&metadata)?)
+}
+
+/// Throw if `config`, a parsed config file, is not a server configuration.
+/// Only its shape is checked; the server checks some sections further when
+/// it starts.
+#[wasm_bindgen(js_name = validateConfig)]
+pub fn validate_config(#[wasm_bindgen(unchecked_param_type = "unknown")] config: JsValue) -> Result<(), JsError> {
+    let file: serde_json::Value = from_js(&config)?;
+    LayeredConfig::new(Some(file), std::iter::empty())?;
+    Ok(())
+}
+
+/// Contents of `hal9.d.ts`: the declaration of every type the bindings take
+/// or return
+#[cfg(feature = "ts")]
+pub fn typescript_declarations() -> String {
+    use hal9_api_types::{
+        ApprovalGate, ChainStatus, CitedMemory, DeferredBranch, ErrorDetails, FanOutTruncation, FanOutUsage,
+        FeedbackEdge, GateStatus, NodeCitations, OutputFormat, ToolLimit, ToolUsage, ToolViolation, TruncationReason,
+    };
+    use hal9_core::metadata_schema::{KeySpec, MetadataType, MetadataViolation, NamespaceDescription, SchemaDescription};
+    use hal9_core::{Activation, PropagationType, SignalPayload};
+    use ts_rs::TS;
+
+    fn declare<T: TS + 'static>(out: &mut String) {
+        if let Some(docs) = T::DOCS {
+            out.push_str(docs);
+        }
+        out.push_str("export ");
+        out.push_str(&T::decl());
+        out.push_str("\n\n");
+    }
+
+    let mut out = String::from("// Generated from the Rust types by hal9-core-wasm; do not edit.\n\n");
+    declare::<NeuronSignal>(&mut out);
+    declare::<PropagationType>(&mut out);
+    declare::<SignalPayload>(&mut out);
+    declare::<Activation>(&mut out);
+    declare::<Gradient>(&mut out);
+    declare::<ChainResult>(&mut out);
+    declare::<ChainStatus>(&mut out);
+    declare::<OutputFormat>(&mut out);
+    declare::<FeedbackEdge>(&mut out);
+    declare::<FanOutUsage>(&mut out);
+    declare::<FanOutTruncation>(&mut out);
+    declare::<TruncationReason>(&mut out);
+    declare::<ToolUsage>(&mut out);
+    declare::<ToolViolation>(&mut out);
+    declare::<ToolLimit>(&mut out);
+    declare::<NodeCitations>(&mut out);
+    declare::<CitedMemory>(&mut out);
+    declare::<DeferredBranch>(&mut out);
+    declare::<ApprovalGate>(&mut out);
+    declare::<GateStatus>(&mut out);
+    declare::<ErrorResponse>(&mut out);
+    declare::<ErrorDetails>(&mut out);
+    declare::<serde_json::Value>(&mut out);
+    declare::<SchemaDescription>(&mut out);
+    declare::<NamespaceDescription>(&mut out);
+    declare::<KeySpec>(&mut out);
+    declare::<MetadataType>(&mut out);
+    declare::<MetadataViolation>(&mut out);
+    declare::<ValidationMode>(&mut out);
+    out.truncate(out.trim_end().len());
+    out.push('\n');
+    out
+}
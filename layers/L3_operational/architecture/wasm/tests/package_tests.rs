@@ -0,0 +1,67 @@
+//! The npm package: hal9.d.ts against the Rust types, and the built module
+//! against its size budget. Run with `--features ts` and `UPDATE_SNAPSHOTS=1`
+//! to rewrite hal9.d.ts after an intended change to one of the types.
+
+use std::path::Path;
+
+use hal9_core_wasm::WASM_SIZE_BUDGET;
+
+#[cfg(feature = "ts")]
+#[test]
+fn test_typescript_declarations_are_current() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("hal9.d.ts");
+    let actual = hal9_core_wasm::typescript_declarations();
+    if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+        std::fs::write(&path, &actual).unwrap();
+        return;
+    }
+    let expected = std::fs::read_to_string(&path).unwrap();
+    assert_eq!(actual, expected, "hal9.d.ts changed; rerun with UPDATE_SNAPSHOTS=1 to accept");
+}
+
+/// Every type the declared ones refer to is declared as well
+#[cfg(feature = "ts")]
+#[test]
+fn test_typescript_declarations_are_complete() {
+    use hal9_api_types::{ChainResult, ErrorResponse};
+    use hal9_core::metadata_schema::{MetadataViolation, SchemaDescription, ValidationMode};
+    use hal9_core::NeuronSignal;
+    use ts_rs::TS;
+
+    let dir = tempfile::tempdir().unwrap();
+    NeuronSignal::export_all_to(dir.path()).unwrap();
+    ChainResult::export_all_to(dir.path()).unwrap();
+    ErrorResponse::export_all_to(dir.path()).unwrap();
+    SchemaDescription::export_all_to(dir.path()).unwrap();
+    MetadataViolation::export_all_to(dir.path()).unwrap();
+    ValidationMode::export_all_to(dir.path()).unwrap();
+
+    // Types from other crates, e.g. serde_json's, are written to directories
+    let declarations = hal9_core_wasm::typescript_declarations();
+    let mut dirs = vec![dir.path().to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        for entry in std::fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                dirs.push(path);
+                continue;
+            }
+            let name = path.file_stem().unwrap().to_str().unwrap();
+            assert!(declarations.contains(&format!("export type {} =", name)), "{} is not declared", name);
+        }
+    }
+}
+
+#[test]
+#[ignore = "needs the module built by `wasm-pack build --release` in pkg/"]
+fn test_wasm_module_stays_under_size_budget() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("pkg/hal9_core_wasm_bg.wasm");
+    let size = std::fs::metadata(&path).unwrap_or_else(|e| panic!("{}: {}", path.display(), e)).len();
+    assert!(
+        size <= WASM_SIZE_BUDGET,
+        "{} is {} bytes, over the budget of {}",
+        path.display(),
+        size,
+        WASM_SIZE_BUDGET
+    );
+}
@@ -0,0 +1,169 @@
+//! Round trips of every type the bindings expose, run in a JavaScript
+//! engine with `wasm-pack test --node layers/L3_operational/architecture/wasm`
+
+#![cfg(target_arch = "wasm32")]
+
+use serde_json::{json, Value};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_test::wasm_bindgen_test;
+
+use hal9_core_wasm::*;
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_namespace = JSON, js_name = parse)]
+    fn json_parse(text: &str) -> JsValue;
+
+    #[wasm_bindgen(js_namespace = JSON, js_name = stringify)]
+    fn json_stringify(value: &JsValue) -> String;
+
+    /// `Error: <message>` for a thrown error
+    #[wasm_bindgen(js_name = String)]
+    fn js_string(value: &JsValue) -> String;
+}
+
+fn to_js(value: &Value) -> JsValue {
+    json_parse(&value.to_string())
+}
+
+fn from_js(value: &JsValue) -> Value {
+    serde_json::from_str(&json_stringify(value)).unwrap()
+}
+
+fn message(error: JsError) -> String {
+    js_string(&error.into())
+}
+
+#[wasm_bindgen_test]
+fn test_signals_round_trip() {
+    let forward = from_js(&forward_signal("planner", "coder", "L4", "L2", "Implement the cart".to_string()).unwrap());
+    assert_eq!(forward["schema_version"], 3);
+    assert_eq!(forward["propagation_type"], "Forward");
+    assert_eq!(forward["payload"]["activation"]["content"], "Implement the cart");
+    assert_eq!(from_js(&parse_signal(to_js(&forward)).unwrap()), forward);
+
+    let gradient = json!({"error_type": "timeout", "magnitude": 0.5, "adjustments": ["retry"], "loss": 0.5});
+    let backward = from_js(&backward_signal("coder", "planner", "L2", "L4", to_js(&gradient)).unwrap());
+    assert_eq!(backward["propagation_type"], "Backward");
+    assert_eq!(backward["payload"]["gradient"], gradient);
+    assert_eq!(from_js(&parse_signal(to_js(&backward)).unwrap()), backward);
+}
+
+#[wasm_bindgen_test]
+fn test_signal_schema_versions() {
+    let mut signal = from_js(&forward_signal("planner", "coder", "L4", "L2", "Plan".to_string()).unwrap());
+    signal["metadata"] = json!({"trace.chain_id": "chain-1"});
+
+    // Version 1 has no schema_version and the ad-hoc metadata keys
+    let legacy = from_js(&downgrade_signal(to_js(&signal), 1).unwrap());
+    assert!(legacy.get("schema_version").is_none());
+    assert_eq!(legacy["metadata"], json!({"chain_id": "chain-1"}));
+    assert_eq!(from_js(&parse_signal(to_js(&legacy)).unwrap()), signal);
+
+    signal["schema_version"] = json!(99);
+    assert!(message(parse_signal(to_js(&signal)).unwrap_err()).contains("newer than this server reads"));
+    assert!(parse_signal(to_js(&json!({"content": "not a signal"}))).is_err());
+}
+
+#[wasm_bindgen_test]
+fn test_chain_results_round_trip() {
+    let result = json!({
+        "chain_id": "chain-1",
+        "status": "completed",
+        "input": "Order the parts",
+        "steps_completed": 3,
+        "pending_signals": 0,
+        "layers": ["L4", "L2"],
+        "final_output": "Parts ordered",
+        "output_format": "json",
+        "format_degraded": true,
+        "format_reason": "missing field `total`",
+        "errors": ["buyer: Rejected at approval gate by bob: Over budget"],
+        "backward_steps": 1,
+        "feedback": [{"from_neuron": "coder", "to_neuron": "planner", "error_class": "timeout", "magnitude": 0.5, "attenuation": 0.8}],
+        "replay_of": "chain-0",
+        "tags": {"project": "checkout"},
+        "fan_out": {
+            "node_budget": 20,
+            "nodes": 3,
+            "truncated_branches": 1,
+            "truncations": [{"signal_id": "s1", "neuron_id": "planner", "layer": "L4", "requested": 3, "allowed": 2, "reason": "max_children", "failed": false}]
+        },
+        "cited_memories": [{"signal_id": "s2", "neuron_id": "coder", "layer": "L2", "memories": [{"reference": "mem-1a2b3c4d", "memory_id": "m1", "namespace": "default"}]}],
+        "deferred": [{"signal_id": "s3", "neuron_id": "coder", "layer": "L2", "reason": "deferred: provider outage", "deferred_at": "2024-05-08T12:00:00Z", "resumed_at": "2024-05-08T12:05:00Z"}],
+        "gates": [{
+            "approval_id": "a1", "signal_id": "s4", "parent_id": "s1", "neuron_id": "buyer", "layer": "L2",
+            "reason": "submits a payment form", "status": "rejected", "decided_by": "bob", "edited": false,
+            "requested_at": "2024-05-08T12:00:00Z", "decided_at": "2024-05-08T12:01:00Z"
+        }],
+        "prompt_tokens": 1200,
+        "completion_tokens": 300,
+        "created_at": "2024-05-08T12:00:00Z",
+        "completed_at": "2024-05-08T12:06:00Z",
+        "duration_ms": 360000,
+        "archived": false
+    });
+    assert_eq!(from_js(&parse_chain_result(to_js(&result)).unwrap()), result);
+
+    // Fields older servers leave out are filled in
+    let mut minimal = result.clone();
+    for key in ["output_format", "format_degraded", "format_reason", "backward_steps", "feedback", "replay_of", "tags", "fan_out", "cited_memories", "deferred", "gates", "archived"] {
+        minimal.as_object_mut().unwrap().remove(key);
+    }
+    let parsed = from_js(&parse_chain_result(to_js(&minimal)).unwrap());
+    assert_eq!((parsed["backward_steps"].clone(), parsed["archived"].clone()), (json!(0), json!(false)));
+
+    assert!(message(parse_chain_result(to_js(&json!({"status": "finished"}))).unwrap_err()).contains("unknown variant"));
+}
+
+#[wasm_bindgen_test]
+fn test_errors_round_trip() {
+    let response = json!({
+        "error": {"code": "RATE_LIMITED", "message": "Too many requests", "error_id": "e1", "details": {"limit": 10}},
+        "retry_after": 30,
+        "help_url": "https://docs.hal9.ai/errors/rate-limited"
+    });
+    assert_eq!(from_js(&parse_error_response(to_js(&response)).unwrap()), response);
+
+    assert!(is_retryable("RATE_LIMITED"));
+    assert!(!is_retryable("INVALID_INPUT"));
+    assert!(!is_retryable("SOMETHING_NEW"));
+    assert_eq!(error_code_for_status(503), "SERVICE_UNAVAILABLE");
+    assert_eq!(error_code_for_status(418), "UNKNOWN_ERROR");
+}
+
+#[wasm_bindgen_test]
+fn test_metadata_validation() {
+    let schema = from_js(&metadata_schema().unwrap());
+    assert_eq!(schema["mode"], "permissive");
+    let trace = schema["namespaces"].as_array().unwrap().iter().find(|ns| ns["name"] == "trace").unwrap();
+    assert!(trace["keys"].as_array().unwrap().iter().any(|key| key["key"] == "trace.chain_id"));
+
+    let metadata = json!({"trace.chain_id": "chain-1", "network.hop_count": "two", "team.owner": "web"});
+    assert_eq!(
+        from_js(&check_metadata(to_js(&metadata)).unwrap()),
+        json!([
+            {"kind": "invalid_value", "key": "network.hop_count", "expected": "integer", "value": "two"},
+            {"kind": "unknown_namespace", "key": "team.owner"}
+        ])
+    );
+
+    let unregistered = to_js(&json!({"team.owner": "web"}));
+    assert!(validate_metadata(unregistered.clone(), to_js(&json!("permissive"))).is_ok());
+    assert!(message(validate_metadata(unregistered, to_js(&json!("strict"))).unwrap_err()).contains("team.owner"));
+    assert!(validate_metadata(to_js(&metadata), to_js(&json!("permissive"))).is_err());
+}
+
+#[wasm_bindgen_test]
+fn test_config_validation() {
+    let config = json!({
+        "server_id": "hal9-dashboard",
+        "neurons": [{"id": "strategic-1", "layer": "L4", "forward_connections": [], "backward_connections": []}],
+        "monitoring": {"log_level": "warn"}
+    });
+    assert!(validate_config(to_js(&config)).is_ok());
+    assert!(message(validate_config(to_js(&json!({}))).unwrap_err()).contains("missing field `server_id`"));
+    assert!(message(validate_config(to_js(&json!(["not", "a", "mapping"]))).unwrap_err()).contains("mapping"));
+    let invalid = json!({"server_id": "hal9-dashboard", "neurons": "strategic-1"});
+    assert!(message(validate_config(to_js(&invalid)).unwrap_err()).contains("Invalid configuration"));
+}
@@ -61,7 +61,7 @@ fi
 # Test 2: Neurons
 echo ""
 echo "🧪 Test 2: Are neurons connected?"
-NEURON_COUNT=$(curl -s http://localhost:8080/api/v1/neurons | jq length 2>/dev/null)
+NEURON_COUNT=$(curl -s http://localhost:8080/api/v1/neurons | jq .data.total_estimate 2>/dev/null)
 if [ "$NEURON_COUNT" = "3" ]; then
     echo -e "${GREEN}✅ YES! 3 neurons active${NC}"
 else
@@ -1,156 +1,112 @@
 use anyhow::Result;
-use reqwest::{Client, Url};
+use hal9_client::profile::ResolvedProfile;
+use hal9_client::{ClientConfig, Credentials, Hal9Client};
 use serde::{Deserialize, Serialize};
 use std::time::Duration;
 use tracing::debug;
 
+/// Server used when neither `--server` nor a profile names one
+pub const DEFAULT_SERVER: &str = "http://localhost:3030";
+
+/// The HAL9 server to migrate and the API key to send it
+#[derive(Debug, Clone)]
+pub struct Server {
+    pub url: String,
+    pub api_key: Option<String>,
+}
+
+impl Server {
+    /// `--server` if given, else the profile's server, else the default.
+    /// The profile's API key is only sent to the profile's own server.
+    pub fn resolve(url: Option<String>, profile: Option<&ResolvedProfile>) -> Self {
+        match (url, profile) {
+            (Some(url), profile) => {
+                let api_key = profile.filter(|p| p.server == url).and_then(|p| p.api_key.clone());
+                Self { url, api_key }
+            }
+            (None, Some(profile)) => Self { url: profile.server.clone(), api_key: profile.api_key.clone() },
+            (None, None) => Self { url: DEFAULT_SERVER.to_string(), api_key: None },
+        }
+    }
+}
+
 /// Client for communicating with HAL9 migration API
 pub struct MigrationClient {
-    client: Client,
-    base_url: Url,
+    client: Hal9Client,
 }
 
 impl MigrationClient {
-    pub fn new(server: &str) -> Result<Self> {
-        let client = Client::builder()
-            .timeout(Duration::from_secs(30))
-            .build()?;
-        
-        let base_url = Url::parse(server)?;
+    pub fn new(server: &Server) -> Result<Self> {
+        let mut config = ClientConfig::new(&server.url).timeout(Duration::from_secs(30));
+        if let Some(key) = &server.api_key {
+            config = config.credentials(Credentials::ApiKey(key.clone()));
+        }
         
-        Ok(Self { client, base_url })
+        Ok(Self { client: Hal9Client::new(config)? })
     }
     
     /// Get current migration status
     pub async fn get_status(&self) -> Result<MigrationStatusResponse> {
-        let url = self.base_url.join("/api/migration/status")?;
-        debug!("Fetching migration status from: {}", url);
-        
-        let response = self.client
-            .get(url)
-            .send()
-            .await?
-            .error_for_status()?
-            .json()
-            .await?;
-        
-        Ok(response)
+        debug!("Fetching migration status");
+        Ok(self.client.get("/api/migration/status", &[]).await?)
     }
     
     /// Start migration phase
     pub async fn start_migration(&self, request: StartMigrationRequest) -> Result<MigrationResponse> {
-        let url = self.base_url.join("/api/migration/start")?;
         debug!("Starting migration: {:?}", request);
-        
-        let response = self.client
-            .post(url)
-            .json(&request)
-            .send()
-            .await?
-            .error_for_status()?
-            .json()
-            .await?;
-        
-        Ok(response)
+        Ok(self.client.post("/api/migration/start", &request).await?)
     }
     
     /// Rollback migration
     pub async fn rollback(&self, request: RollbackRequest) -> Result<MigrationResponse> {
-        let url = self.base_url.join("/api/migration/rollback")?;
         debug!("Rolling back migration: {:?}", request);
-        
-        let response = self.client
-            .post(url)
-            .json(&request)
-            .send()
-            .await?
-            .error_for_status()?
-            .json()
-            .await?;
-        
-        Ok(response)
+        Ok(self.client.post("/api/migration/rollback", &request).await?)
     }
     
     /// Get health checks
     pub async fn get_health(&self) -> Result<Vec<HealthCheckResponse>> {
-        let url = self.base_url.join("/api/health")?;
-        debug!("Fetching health status from: {}", url);
-        
-        let response = self.client
-            .get(url)
-            .send()
-            .await?
-            .error_for_status()?
-            .json()
-            .await?;
-        
-        Ok(response)
+        debug!("Fetching health status");
+        Ok(self.client.get("/api/health", &[]).await?)
     }
     
     /// Get feature flags
     pub async fn get_features(&self) -> Result<Vec<FeatureFlagResponse>> {
-        let url = self.base_url.join("/api/migration/features")?;
-        debug!("Fetching feature flags from: {}", url);
-        
-        let response = self.client
-            .get(url)
-            .send()
-            .await?
-            .error_for_status()?
-            .json()
-            .await?;
-        
-        Ok(response)
+        debug!("Fetching feature flags");
+        Ok(self.client.get("/api/migration/features", &[]).await?)
     }
     
     /// Update feature flag
     pub async fn update_feature(&self, name: &str, request: UpdateFeatureRequest) -> Result<FeatureFlagResponse> {
-        let url = self.base_url.join(&format!("/api/migration/features/{}", name))?;
         debug!("Updating feature {}: {:?}", name, request);
-        
-        let response = self.client
-            .put(url)
-            .json(&request)
-            .send()
-            .await?
-            .error_for_status()?
-            .json()
-            .await?;
-        
-        Ok(response)
+        Ok(self.client.put(&format!("/api/migration/features/{}", name), &request).await?)
     }
     
     /// Export migration state
     pub async fn export_state(&self) -> Result<MigrationStateExport> {
-        let url = self.base_url.join("/api/migration/state/export")?;
         debug!("Exporting migration state");
-        
-        let response = self.client
-            .get(url)
-            .send()
-            .await?
-            .error_for_status()?
-            .json()
-            .await?;
-        
-        Ok(response)
+        Ok(self.client.get("/api/migration/state/export", &[]).await?)
     }
     
     /// Import migration state
     pub async fn import_state(&self, state: MigrationStateExport) -> Result<MigrationResponse> {
-        let url = self.base_url.join("/api/migration/state/import")?;
         debug!("Importing migration state");
-        
-        let response = self.client
-            .post(url)
-            .json(&state)
-            .send()
-            .await?
-            .error_for_status()?
-            .json()
-            .await?;
-        
-        Ok(response)
+        Ok(self.client.post("/api/migration/state/import", &state).await?)
+    }
+    
+    /// Get sampled captures of mirrored traffic
+    pub async fn get_captures(&self) -> Result<Vec<TrafficCapture>> {
+        debug!("Fetching traffic captures");
+        Ok(self.client.get("/api/migration/captures", &[]).await?)
+    }
+    
+    /// Delete captures taken before `before`, or all of them
+    pub async fn purge_captures(&self, before: Option<chrono::DateTime<chrono::Utc>>) -> Result<PurgeCapturesResponse> {
+        let query: Vec<(String, String)> = before
+            .map(|before| ("before".to_string(), before.to_rfc3339()))
+            .into_iter()
+            .collect();
+        debug!("Purging traffic captures: {:?}", query);
+        Ok(self.client.delete("/api/migration/captures", &query).await?)
     }
 }
 
@@ -234,4 +190,24 @@ pub struct MigrationCheckpoint {
     pub created_at: chrono::DateTime<chrono::Utc>,
     pub phase: String,
     pub description: Option<String>,
-}
\ No newline at end of file
+}
+/// A mirrored request as captured by the server; payloads and responses
+/// are not needed for the summary and are ignored
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrafficCapture {
+    pub layer: String,
+    pub flat: PathSample,
+    pub hierarchical: PathSample,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PathSample {
+    pub latency_ms: f64,
+    pub response_length: usize,
+    pub error_class: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PurgeCapturesResponse {
+    pub deleted: usize,
+}
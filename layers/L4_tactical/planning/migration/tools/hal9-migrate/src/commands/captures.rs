@@ -0,0 +1,60 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use colored::Colorize;
+use dialoguer::{Confirm, theme::ColorfulTheme};
+use tracing::info;
+
+use crate::OutputFormat;
+use crate::client::{MigrationClient, Server};
+use super::format_output;
+
+/// Delete captured traffic in bulk, e.g. to honour a data deletion request
+pub async fn purge(
+    server: &Server,
+    before: Option<String>,
+    yes: bool,
+    format: &OutputFormat,
+) -> Result<()> {
+    let client = MigrationClient::new(server)?;
+    let before = before
+        .map(|before| {
+            DateTime::parse_from_rfc3339(&before)
+                .map(|before| before.with_timezone(&Utc))
+                .with_context(|| format!("Invalid --before timestamp {}, expected RFC 3339", before))
+        })
+        .transpose()?;
+    
+    if matches!(format, OutputFormat::Pretty) {
+        println!("{}", "🗑️  Purge Traffic Captures".bold());
+        println!();
+        
+        match before {
+            Some(before) => println!("Deleting captures taken before {}", before.to_rfc3339()),
+            None => println!("Deleting {} captures", "all".bold()),
+        }
+        
+        if !yes {
+            let confirmation = Confirm::with_theme(&ColorfulTheme::default())
+                .with_prompt("Captures cannot be recovered. Continue?")
+                .default(false)
+                .interact()?;
+            
+            if !confirmation {
+                println!("{}", "Purge cancelled.".yellow());
+                return Ok(());
+            }
+        }
+    }
+    
+    info!("Purging traffic captures");
+    let result = client.purge_captures(before).await?;
+    
+    match format {
+        OutputFormat::Pretty | OutputFormat::Table => {
+            println!("{}", format!("✅ Deleted {} captures", result.deleted).green().bold());
+        }
+        OutputFormat::Json => format_output(&result, format)?,
+    }
+    
+    Ok(())
+}
@@ -4,9 +4,9 @@ use comfy_table::{Table, Cell, Attribute};
 use tracing::info;
 
 use crate::OutputFormat;
-use crate::client::MigrationClient;
+use crate::client::{MigrationClient, Server};
 
-pub async fn list(server: &str, format: &OutputFormat) -> Result<()> {
+pub async fn list(server: &Server, format: &OutputFormat) -> Result<()> {
     let client = MigrationClient::new(server)?;
     info!("Listing feature flags");
     
@@ -54,7 +54,7 @@ pub async fn list(server: &str, format: &OutputFormat) -> Result<()> {
 }
 
 pub async fn enable(
-    server: &str,
+    server: &Server,
     name: &str,
     percentage: Option<u8>,
     format: &OutputFormat,
@@ -72,7 +72,7 @@ pub async fn enable(
     Ok(())
 }
 
-pub async fn disable(server: &str, name: &str, format: &OutputFormat) -> Result<()> {
+pub async fn disable(server: &Server, name: &str, format: &OutputFormat) -> Result<()> {
     let client = MigrationClient::new(server)?;
     info!("Disabling feature flag: {}", name);
     
@@ -83,7 +83,7 @@ pub async fn disable(server: &str, name: &str, format: &OutputFormat) -> Result<
     Ok(())
 }
 
-pub async fn status(server: &str, name: &str, format: &OutputFormat) -> Result<()> {
+pub async fn status(server: &Server, name: &str, format: &OutputFormat) -> Result<()> {
     let client = MigrationClient::new(server)?;
     info!("Getting status for feature: {}", name);
     
@@ -6,10 +6,10 @@ use std::time::Duration;
 use tracing::{info, warn};
 
 use crate::{MigrationPhase, OutputFormat};
-use crate::client::MigrationClient;
+use crate::client::{MigrationClient, Server};
 
 pub async fn run(
-    server: &str,
+    server: &Server,
     phase: MigrationPhase,
     percentage: Option<u8>,
     dry_run: bool,
@@ -4,6 +4,7 @@ pub mod migrate;
 pub mod rollback;
 pub mod verify;
 pub mod feature;
+pub mod captures;
 
 use serde::{Deserialize, Serialize};
 use std::fmt;
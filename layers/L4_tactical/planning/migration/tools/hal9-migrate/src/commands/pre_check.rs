@@ -5,12 +5,12 @@ use indicatif::{ProgressBar, ProgressStyle};
 use std::time::Duration;
 use tracing::{info, warn};
 
-use crate::client::MigrationClient;
+use crate::client::{MigrationClient, Server};
 use crate::OutputFormat;
 use super::{HealthCheck, HealthStatus, format_output};
 
 pub async fn run(
-    server: &str,
+    server: &Server,
     deep: bool,
     components: Vec<String>,
     format: &OutputFormat,
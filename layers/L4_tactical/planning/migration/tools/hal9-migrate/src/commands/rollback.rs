@@ -4,10 +4,10 @@ use dialoguer::{Confirm, theme::ColorfulTheme};
 use tracing::info;
 
 use crate::{MigrationPhase, OutputFormat};
-use crate::client::MigrationClient;
+use crate::client::{MigrationClient, Server};
 
 pub async fn run(
-    server: &str,
+    server: &Server,
     to_phase: Option<MigrationPhase>,
     force: bool,
     yes: bool,
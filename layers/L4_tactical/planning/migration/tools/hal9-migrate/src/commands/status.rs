@@ -6,12 +6,12 @@ use indicatif::{ProgressBar, ProgressStyle};
 use std::time::Duration;
 use tracing::info;
 
-use crate::client::MigrationClient;
+use crate::client::{MigrationClient, Server};
 use crate::OutputFormat;
 use super::{MigrationStatus, MigrationMetrics, format_output};
 
 pub async fn show(
-    server: &str,
+    server: &Server,
     detailed: bool,
     format: &OutputFormat,
 ) -> Result<()> {
@@ -31,7 +31,7 @@ pub async fn show(
 }
 
 pub async fn watch(
-    server: &str,
+    server: &Server,
     detailed: bool,
     interval: u64,
     format: &OutputFormat,
@@ -1,12 +1,15 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
 use colored::Colorize;
+use comfy_table::{Table, Cell, Attribute};
 use tracing::info;
 
 use crate::OutputFormat;
-use crate::client::MigrationClient;
+use crate::client::{MigrationClient, Server, TrafficCapture};
+use crate::divergence::{CaptureSummary, Comparison};
+use super::format_output;
 
 pub async fn run(
-    server: &str,
+    server: &Server,
     full: bool,
     tests: Vec<String>,
     report: bool,
@@ -40,4 +43,107 @@ pub async fn run(
     }
     
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Compare the flat and hierarchical paths over captured mirrored traffic,
+/// read from the server or from an exported captures file
+pub async fn captures(
+    server: &Server,
+    file: Option<&str>,
+    alpha: f64,
+    format: &OutputFormat,
+) -> Result<()> {
+    let captures: Vec<TrafficCapture> = match file {
+        Some(path) => {
+            let json = std::fs::read_to_string(path)
+                .with_context(|| format!("Failed to read captures from {}", path))?;
+            serde_json::from_str(&json)
+                .with_context(|| format!("Invalid captures file {}", path))?
+        }
+        None => MigrationClient::new(server)?.get_captures().await?,
+    };
+    
+    info!("Comparing {} traffic captures", captures.len());
+    let summary = CaptureSummary::new(&captures, alpha);
+    
+    match format {
+        OutputFormat::Pretty | OutputFormat::Table => display_summary(&summary),
+        OutputFormat::Json => format_output(&summary, format)?,
+    }
+    
+    if !summary.divergences.is_empty() {
+        anyhow::bail!("{} significant divergence(s) between flat and hierarchical paths", summary.divergences.len());
+    }
+    Ok(())
+}
+
+fn display_summary(summary: &CaptureSummary) {
+    println!("{}", "🔬 Mirrored Traffic Comparison".bold());
+    println!("{} captures, significance level {}", summary.captures, summary.alpha);
+    println!();
+    
+    if summary.captures == 0 {
+        println!("{}", "No captures yet. Enable shadow mode with a capture sample rate.".yellow());
+        return;
+    }
+    
+    let mut table = Table::new();
+    table.set_header(vec![
+        Cell::new("Metric").add_attribute(Attribute::Bold),
+        Cell::new("Flat p50/p95/p99").add_attribute(Attribute::Bold),
+        Cell::new("Hierarchical p50/p95/p99").add_attribute(Attribute::Bold),
+        Cell::new("KS D").add_attribute(Attribute::Bold),
+        Cell::new("p-value").add_attribute(Attribute::Bold),
+    ]);
+    for (layer, comparison) in &summary.latency {
+        table.add_row(comparison_row(&format!("{} latency (ms)", layer), comparison));
+    }
+    table.add_row(comparison_row("Response length (bytes)", &summary.response_length));
+    println!("{table}");
+    
+    if !summary.errors.is_empty() {
+        println!();
+        let mut errors = Table::new();
+        errors.set_header(vec![
+            Cell::new("Error class").add_attribute(Attribute::Bold),
+            Cell::new("Flat").add_attribute(Attribute::Bold),
+            Cell::new("Hierarchical").add_attribute(Attribute::Bold),
+        ]);
+        for (class, counts) in &summary.errors {
+            errors.add_row(vec![
+                Cell::new(class),
+                Cell::new(counts.flat),
+                Cell::new(counts.hierarchical),
+            ]);
+        }
+        println!("{errors}");
+    }
+    
+    println!();
+    if summary.divergences.is_empty() {
+        println!("{}", "✅ No significant divergence between paths".green().bold());
+    } else {
+        println!("{}", "⚠️  Significant divergences:".red().bold());
+        for divergence in &summary.divergences {
+            println!("  • {}", divergence);
+        }
+    }
+}
+
+fn comparison_row(metric: &str, comparison: &Comparison) -> Vec<Cell> {
+    let percentiles = |p: &crate::divergence::Percentiles| {
+        format!("{:.1} / {:.1} / {:.1} (n={})", p.p50, p.p95, p.p99, p.count)
+    };
+    let (statistic, p_value) = match &comparison.ks {
+        Some(ks) if ks.significant => (format!("{:.3}", ks.statistic), format!("{:.2e}", ks.p_value).red().to_string()),
+        Some(ks) => (format!("{:.3}", ks.statistic), format!("{:.2e}", ks.p_value)),
+        None => ("-".to_string(), "too few samples".dimmed().to_string()),
+    };
+    vec![
+        Cell::new(metric),
+        Cell::new(percentiles(&comparison.flat)),
+        Cell::new(percentiles(&comparison.hierarchical)),
+        Cell::new(statistic),
+        Cell::new(p_value),
+    ]
+}
@@ -52,8 +52,8 @@ struct FeatureFlag {
 }
 
 impl DashboardServer {
-    pub fn new(server_url: &str) -> Result<Self> {
-        let client = Arc::new(crate::client::MigrationClient::new(server_url)?);
+    pub fn new(server: &crate::client::Server) -> Result<Self> {
+        let client = Arc::new(crate::client::MigrationClient::new(server)?);
         let state = Arc::new(RwLock::new(DashboardState::default()));
         
         Ok(Self { client, state })
@@ -0,0 +1,286 @@
+//! Distribution comparison of captured mirrored traffic
+//!
+//! Each capture records how the flat and the hierarchical system handled the
+//! same request. The summary compares the two paths' latency per layer and
+//! response lengths with a two-sample Kolmogorov-Smirnov test, and breaks
+//! down error classes.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::client::TrafficCapture;
+
+/// Samples each path needs before a difference can be flagged
+pub const MIN_SAMPLES: usize = 20;
+
+/// Significance level used when none is given
+pub const DEFAULT_ALPHA: f64 = 0.01;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Percentiles {
+    pub count: usize,
+    pub p50: f64,
+    pub p95: f64,
+    pub p99: f64,
+}
+
+impl Percentiles {
+    fn of(sorted: &[f64]) -> Self {
+        let at = |q: f64| {
+            if sorted.is_empty() {
+                return 0.0;
+            }
+            let rank = ((sorted.len() as f64 * q).ceil() as usize).clamp(1, sorted.len());
+            sorted[rank - 1]
+        };
+        Self { count: sorted.len(), p50: at(0.50), p95: at(0.95), p99: at(0.99) }
+    }
+}
+
+/// Two-sample Kolmogorov-Smirnov test result
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KsTest {
+    /// Largest distance between the two empirical distributions
+    pub statistic: f64,
+    pub p_value: f64,
+    pub significant: bool,
+}
+
+/// One metric on both paths
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Comparison {
+    pub flat: Percentiles,
+    pub hierarchical: Percentiles,
+    /// `None` while either path has fewer than `MIN_SAMPLES` samples
+    pub ks: Option<KsTest>,
+}
+
+impl Comparison {
+    fn new(mut flat: Vec<f64>, mut hierarchical: Vec<f64>, alpha: f64) -> Self {
+        flat.sort_by(f64::total_cmp);
+        hierarchical.sort_by(f64::total_cmp);
+        let ks = (flat.len() >= MIN_SAMPLES && hierarchical.len() >= MIN_SAMPLES).then(|| {
+            let statistic = ks_statistic(&flat, &hierarchical);
+            let p_value = ks_p_value(statistic, flat.len(), hierarchical.len());
+            KsTest { statistic, p_value, significant: p_value < alpha }
+        });
+        Self {
+            flat: Percentiles::of(&flat),
+            hierarchical: Percentiles::of(&hierarchical),
+            ks,
+        }
+    }
+
+    fn diverges(&self) -> bool {
+        self.ks.as_ref().is_some_and(|ks| ks.significant)
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ErrorCounts {
+    pub flat: usize,
+    pub hierarchical: usize,
+}
+
+/// Distributional differences between the two paths
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CaptureSummary {
+    pub captures: usize,
+    pub alpha: f64,
+    /// Latency in ms by layer
+    pub latency: BTreeMap<String, Comparison>,
+    /// Length of successful responses
+    pub response_length: Comparison,
+    /// Failed requests by error class
+    pub errors: BTreeMap<String, ErrorCounts>,
+    /// Human-readable list of significant divergences
+    pub divergences: Vec<String>,
+}
+
+impl CaptureSummary {
+    pub fn new(captures: &[TrafficCapture], alpha: f64) -> Self {
+        let mut latency: BTreeMap<String, (Vec<f64>, Vec<f64>)> = BTreeMap::new();
+        let mut lengths = (Vec::new(), Vec::new());
+        let mut errors: BTreeMap<String, ErrorCounts> = BTreeMap::new();
+
+        for capture in captures {
+            let layer = latency.entry(capture.layer.clone()).or_default();
+            layer.0.push(capture.flat.latency_ms);
+            layer.1.push(capture.hierarchical.latency_ms);
+
+            match &capture.flat.error_class {
+                Some(class) => errors.entry(class.clone()).or_default().flat += 1,
+                None => lengths.0.push(capture.flat.response_length as f64),
+            }
+            match &capture.hierarchical.error_class {
+                Some(class) => errors.entry(class.clone()).or_default().hierarchical += 1,
+                None => lengths.1.push(capture.hierarchical.response_length as f64),
+            }
+        }
+
+        let latency: BTreeMap<_, _> = latency
+            .into_iter()
+            .map(|(layer, (flat, hierarchical))| (layer, Comparison::new(flat, hierarchical, alpha)))
+            .collect();
+        let response_length = Comparison::new(lengths.0, lengths.1, alpha);
+
+        let mut divergences = Vec::new();
+        for (layer, comparison) in &latency {
+            if comparison.diverges() {
+                divergences.push(describe(&format!("{} latency", layer), comparison, "ms"));
+            }
+        }
+        if response_length.diverges() {
+            divergences.push(describe("Response length", &response_length, "bytes"));
+        }
+
+        Self {
+            captures: captures.len(),
+            alpha,
+            latency,
+            response_length,
+            errors,
+            divergences,
+        }
+    }
+}
+
+fn describe(metric: &str, comparison: &Comparison, unit: &str) -> String {
+    let ks = comparison.ks.as_ref().expect("divergent comparisons are tested");
+    format!(
+        "{}: p50 {:.1}{} flat vs {:.1}{} hierarchical (D={:.3}, p={:.2e})",
+        metric, comparison.flat.p50, unit, comparison.hierarchical.p50, unit, ks.statistic, ks.p_value
+    )
+}
+
+/// Largest distance between the empirical CDFs of two sorted samples
+pub fn ks_statistic(a: &[f64], b: &[f64]) -> f64 {
+    let (mut i, mut j, mut d) = (0, 0, 0.0f64);
+    while i < a.len() && j < b.len() {
+        let x = a[i].min(b[j]);
+        while i < a.len() && a[i] <= x {
+            i += 1;
+        }
+        while j < b.len() && b[j] <= x {
+            j += 1;
+        }
+        d = d.max((i as f64 / a.len() as f64 - j as f64 / b.len() as f64).abs());
+    }
+    d
+}
+
+/// Asymptotic p-value of a two-sample KS statistic
+pub fn ks_p_value(d: f64, n: usize, m: usize) -> f64 {
+    let en = ((n * m) as f64 / (n + m) as f64).sqrt();
+    let lambda = (en + 0.12 + 0.11 / en) * d;
+    if lambda < 1e-3 {
+        return 1.0;
+    }
+    // Kolmogorov distribution tail: 2 * sum (-1)^(k-1) exp(-2 k^2 lambda^2)
+    let mut sum = 0.0;
+    for k in 1..=100 {
+        let term = (-2.0 * (k * k) as f64 * lambda * lambda).exp();
+        sum += if k % 2 == 1 { term } else { -term };
+        if term < 1e-12 {
+            break;
+        }
+    }
+    (2.0 * sum).clamp(0.0, 1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::PathSample;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    /// Strict enough that identical distributions are practically never
+    /// flagged, while the skews below give p-values far smaller
+    const ALPHA: f64 = 1e-4;
+
+    fn sample(latency_ms: f64, response_length: usize, error_class: Option<&str>) -> PathSample {
+        PathSample {
+            latency_ms,
+            response_length,
+            error_class: error_class.map(str::to_string),
+        }
+    }
+
+    /// Captures whose hierarchical latency and response length are scaled
+    fn captures(layer: &str, count: usize, latency_scale: f64, length_scale: f64, seed: u64) -> Vec<TrafficCapture> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        (0..count)
+            .map(|_| {
+                let flat_latency = rng.gen_range(20.0..60.0);
+                let hier_latency = rng.gen_range(20.0..60.0) * latency_scale;
+                let flat_length = rng.gen_range(200..400);
+                let hier_length = (rng.gen_range(200..400) as f64 * length_scale) as usize;
+                TrafficCapture {
+                    layer: layer.to_string(),
+                    flat: sample(flat_latency, flat_length, None),
+                    hierarchical: sample(hier_latency, hier_length, None),
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_identical_distributions_are_not_flagged() {
+        let summary = CaptureSummary::new(&captures("L3", 500, 1.0, 1.0, 1), ALPHA);
+        assert!(summary.divergences.is_empty(), "{:?}", summary.divergences);
+        assert!(!summary.latency["L3"].ks.as_ref().unwrap().significant);
+        assert!(!summary.response_length.ks.as_ref().unwrap().significant);
+    }
+
+    #[test]
+    fn test_skewed_latency_is_flagged_per_layer() {
+        let mut all = captures("L2", 400, 1.0, 1.0, 2);
+        all.extend(captures("L4", 400, 1.5, 1.0, 3));
+        let summary = CaptureSummary::new(&all, ALPHA);
+
+        assert!(!summary.latency["L2"].diverges());
+        let l4 = &summary.latency["L4"];
+        assert!(l4.diverges());
+        assert!(l4.hierarchical.p50 > l4.flat.p50);
+        assert_eq!(summary.divergences.len(), 1);
+        assert!(summary.divergences[0].starts_with("L4 latency"));
+    }
+
+    #[test]
+    fn test_skewed_response_length_is_flagged() {
+        let summary = CaptureSummary::new(&captures("L3", 300, 1.0, 0.5, 4), ALPHA);
+        assert!(summary.response_length.diverges());
+        assert!(summary.divergences.iter().any(|d| d.starts_with("Response length")));
+    }
+
+    #[test]
+    fn test_small_samples_are_never_flagged() {
+        let summary = CaptureSummary::new(&captures("L3", MIN_SAMPLES - 1, 10.0, 10.0, 5), ALPHA);
+        assert!(summary.latency["L3"].ks.is_none());
+        assert!(summary.divergences.is_empty());
+    }
+
+    #[test]
+    fn test_error_classes_are_counted_per_path() {
+        let mut all = captures("L3", 10, 1.0, 1.0, 6);
+        all[0].hierarchical = sample(5_000.0, 0, Some("Timeout"));
+        all[1].hierarchical = sample(5_000.0, 0, Some("Timeout"));
+        all[2].flat = sample(3.0, 0, Some("Routing"));
+        let summary = CaptureSummary::new(&all, ALPHA);
+
+        assert_eq!(summary.errors["Timeout"].hierarchical, 2);
+        assert_eq!(summary.errors["Timeout"].flat, 0);
+        assert_eq!(summary.errors["Routing"].flat, 1);
+        assert_eq!(summary.response_length.hierarchical.count, 8);
+        assert_eq!(summary.latency["L3"].hierarchical.count, 10);
+    }
+
+    #[test]
+    fn test_ks_statistic() {
+        assert_eq!(ks_statistic(&[1.0, 2.0, 3.0], &[1.0, 2.0, 3.0]), 0.0);
+        assert_eq!(ks_statistic(&[1.0, 2.0], &[3.0, 4.0]), 1.0);
+        assert!((ks_statistic(&[1.0, 2.0, 3.0, 4.0], &[3.0, 4.0, 5.0, 6.0]) - 0.5).abs() < 1e-12);
+        assert!(ks_p_value(0.0, 100, 100) > 0.99);
+        assert!(ks_p_value(0.5, 100, 100) < 1e-8);
+    }
+}
@@ -1,5 +1,5 @@
 use anyhow::Result;
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
 use colored::Colorize;
 use indicatif::{ProgressBar, ProgressStyle};
 use std::time::Duration;
@@ -12,8 +12,11 @@ mod client;
 mod monitor;
 mod state;
 mod dashboard;
+mod divergence;
 
+use client::Server;
 use commands::{pre_check, status, migrate, rollback, verify};
+use hal9_client::profile::{self, ProfileFile};
 
 /// HAL9 Migration CLI - Production-ready migration tooling for HAL9 hierarchical architecture
 #[derive(Parser)]
@@ -23,13 +26,17 @@ struct Cli {
     #[command(subcommand)]
     command: Commands,
     
-    /// HAL9 server URL (can also be set via HAL9_SERVER env var)
-    #[arg(short, long, env = "HAL9_SERVER", default_value = "http://localhost:3030")]
-    server: String,
+    /// HAL9 server URL (can also be set via HAL9_SERVER env var) [default: the profile's, else http://localhost:3030]
+    #[arg(short, long, env = "HAL9_SERVER")]
+    server: Option<String>,
     
-    /// Output format (json, table, pretty)
-    #[arg(short, long, default_value = "pretty")]
-    format: OutputFormat,
+    /// Profile from ~/.config/hal9/config.toml, shared with the hal9 CLI [default: $HAL9_PROFILE, else the file's default]
+    #[arg(long)]
+    profile: Option<String>,
+    
+    /// Output format (json, table, pretty) [default: the profile's if one of these, else pretty]
+    #[arg(short, long)]
+    format: Option<OutputFormat>,
     
     /// Enable verbose logging
     #[arg(short, long)]
@@ -107,6 +114,10 @@ enum Commands {
         /// Auto-approve rollback (skip confirmation)
         #[arg(long)]
         yes: bool,
+        
+        /// Roll back a production profile's server without asking
+        #[arg(long)]
+        yes_production: bool,
     },
     
     /// Verify migration integrity and performance
@@ -122,6 +133,18 @@ enum Commands {
         /// Generate detailed report
         #[arg(long)]
         report: bool,
+        
+        /// Compare flat and hierarchical paths over captured mirrored traffic
+        #[arg(long)]
+        captures: bool,
+        
+        /// Read captures from an exported JSON file instead of the server
+        #[arg(long, requires = "captures")]
+        captures_file: Option<String>,
+        
+        /// Significance level for flagging divergences
+        #[arg(long, default_value_t = divergence::DEFAULT_ALPHA, requires = "captures")]
+        alpha: f64,
     },
     
     /// Monitor live migration metrics
@@ -141,6 +164,12 @@ enum Commands {
         command: FeatureCommands,
     },
     
+    /// Manage captured mirrored traffic
+    Captures {
+        #[command(subcommand)]
+        command: CaptureCommands,
+    },
+    
     /// Export/import migration state
     State {
         #[command(subcommand)]
@@ -200,6 +229,24 @@ enum FeatureCommands {
     },
 }
 
+#[derive(Subcommand)]
+enum CaptureCommands {
+    /// Delete captures in bulk
+    Purge {
+        /// Only delete captures taken before this RFC 3339 timestamp
+        #[arg(long)]
+        before: Option<String>,
+        
+        /// Skip confirmation
+        #[arg(long)]
+        yes: bool,
+        
+        /// Purge a production profile's server without asking
+        #[arg(long)]
+        yes_production: bool,
+    },
+}
+
 #[derive(Subcommand)]
 enum StateCommands {
     /// Export current migration state
@@ -254,6 +301,14 @@ enum StateCommands {
 async fn main() -> Result<()> {
     let cli = Cli::parse();
     
+    // Flags win over the profile, which wins over the defaults
+    let profiles = ProfileFile::load_default()?;
+    let selected = profiles.resolve_from_env(cli.profile.as_deref())?;
+    let server = Server::resolve(cli.server, selected.as_ref());
+    let format = cli.format
+        .or_else(|| selected.as_ref()?.output.as_deref().and_then(|o| OutputFormat::from_str(o, true).ok()))
+        .unwrap_or(OutputFormat::Pretty);
+    
     // Initialize tracing
     let filter = if cli.verbose {
         "hal9_migrate=debug,hal9=debug,info"
@@ -267,7 +322,7 @@ async fn main() -> Result<()> {
         .init();
     
     // ASCII art banner
-    if matches!(cli.format, OutputFormat::Pretty) {
+    if matches!(format, OutputFormat::Pretty) {
         println!("{}", r#"
 ╦ ╦╔═╗╦  ╔═╗  ╔╦╗╦╔═╗╦═╗╔═╗╔╦╗╔═╗
 ╠═╣╠═╣║  ╚═╗  ║║║║║ ╦╠╦╝╠═╣ ║ ║╣ 
@@ -279,54 +334,68 @@ async fn main() -> Result<()> {
     // Execute command
     match cli.command {
         Commands::PreCheck { deep, components } => {
-            pre_check::run(&cli.server, deep, components, &cli.format).await?;
+            pre_check::run(&server, deep, components, &format).await?;
         }
         
         Commands::Status { detailed, watch, interval } => {
             if watch {
-                status::watch(&cli.server, detailed, interval, &cli.format).await?;
+                status::watch(&server, detailed, interval, &format).await?;
             } else {
-                status::show(&cli.server, detailed, &cli.format).await?;
+                status::show(&server, detailed, &format).await?;
             }
         }
         
         Commands::Migrate { phase, percentage, dry_run, yes, timeout } => {
             migrate::run(
-                &cli.server,
+                &server,
                 phase,
                 percentage,
                 dry_run,
                 yes,
                 timeout,
-                &cli.format
+                &format
             ).await?;
         }
         
-        Commands::Rollback { to_phase, force, yes } => {
-            rollback::run(&cli.server, to_phase, force, yes, &cli.format).await?;
+        Commands::Rollback { to_phase, force, yes, yes_production } => {
+            guard_production(&profiles, &server, "roll back the migration", yes_production)?;
+            rollback::run(&server, to_phase, force, yes, &format).await?;
         }
         
-        Commands::Verify { full, tests, report } => {
-            verify::run(&cli.server, full, tests, report, &cli.format).await?;
+        Commands::Verify { full, tests, report, captures, captures_file, alpha } => {
+            if captures {
+                verify::captures(&server, captures_file.as_deref(), alpha, &format).await?;
+            } else {
+                verify::run(&server, full, tests, report, &format).await?;
+            }
         }
         
         Commands::Monitor { dashboard, interval } => {
-            monitor::run(&cli.server, dashboard, interval).await?;
+            monitor::run(&server, dashboard, interval).await?;
         }
         
         Commands::Feature { command } => {
             match command {
                 FeatureCommands::List => {
-                    commands::feature::list(&cli.server, &cli.format).await?;
+                    commands::feature::list(&server, &format).await?;
                 }
                 FeatureCommands::Enable { name, percentage } => {
-                    commands::feature::enable(&cli.server, &name, percentage, &cli.format).await?;
+                    commands::feature::enable(&server, &name, percentage, &format).await?;
                 }
                 FeatureCommands::Disable { name } => {
-                    commands::feature::disable(&cli.server, &name, &cli.format).await?;
+                    commands::feature::disable(&server, &name, &format).await?;
                 }
                 FeatureCommands::Status { name } => {
-                    commands::feature::status(&cli.server, &name, &cli.format).await?;
+                    commands::feature::status(&server, &name, &format).await?;
+                }
+            }
+        }
+        
+        Commands::Captures { command } => {
+            match command {
+                CaptureCommands::Purge { before, yes, yes_production } => {
+                    guard_production(&profiles, &server, "purge captured traffic", yes_production)?;
+                    commands::captures::purge(&server, before, yes, &format).await?;
                 }
             }
         }
@@ -334,29 +403,39 @@ async fn main() -> Result<()> {
         Commands::State { command } => {
             match command {
                 StateCommands::Export { output, include_sensitive } => {
-                    state::export(&cli.server, &output, include_sensitive, &cli.format).await?;
+                    state::export(&server, &output, include_sensitive, &format).await?;
                 }
                 StateCommands::Import { input, validate_only } => {
-                    state::import(&cli.server, &input, validate_only, &cli.format).await?;
+                    state::import(&server, &input, validate_only, &format).await?;
                 }
                 StateCommands::Checkpoint { name, description } => {
-                    state::checkpoint(&cli.server, &name, description, &cli.format).await?;
+                    state::checkpoint(&server, &name, description, &format).await?;
                 }
                 StateCommands::ListCheckpoints => {
-                    state::list_checkpoints(&cli.server, &cli.format).await?;
+                    state::list_checkpoints(&server, &format).await?;
                 }
                 StateCommands::Restore { checkpoint, force } => {
-                    state::restore(&cli.server, &checkpoint, force, &cli.format).await?;
+                    state::restore(&server, &checkpoint, force, &format).await?;
                 }
             }
         }
         
         Commands::Dashboard { port } => {
             info!("Starting migration dashboard on port {}", port);
-            let server = dashboard::DashboardServer::new(&cli.server)?;
+            let server = dashboard::DashboardServer::new(&server)?;
             server.run(port).await?;
         }
     }
     
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Let a destructive `action` proceed. Against a production profile's
+/// server it needs `--yes-production` as `confirmed`, or typing the profile
+/// name on the terminal.
+fn guard_production(profiles: &ProfileFile, server: &Server, action: &str, confirmed: bool) -> Result<()> {
+    if let Some(name) = profiles.production_for(&server.url) {
+        profile::confirm_production_interactive(name, &server.url, action, confirmed)?;
+    }
+    Ok(())
+}
@@ -4,10 +4,10 @@ use std::io::{self, Write};
 use tracing::info;
 
 use crate::DashboardType;
-use crate::client::MigrationClient;
+use crate::client::{MigrationClient, Server};
 
 pub async fn run(
-    server: &str,
+    server: &Server,
     dashboard: DashboardType,
     interval: u64,
 ) -> Result<()> {
@@ -5,10 +5,10 @@ use std::path::Path;
 use tracing::info;
 
 use crate::OutputFormat;
-use crate::client::{MigrationClient, MigrationStateExport};
+use crate::client::{MigrationClient, MigrationStateExport, Server};
 
 pub async fn export(
-    server: &str,
+    server: &Server,
     output: &str,
     include_sensitive: bool,
     format: &OutputFormat,
@@ -47,7 +47,7 @@ pub async fn export(
 }
 
 pub async fn import(
-    server: &str,
+    server: &Server,
     input: &str,
     validate_only: bool,
     format: &OutputFormat,
@@ -78,7 +78,7 @@ pub async fn import(
 }
 
 pub async fn checkpoint(
-    server: &str,
+    server: &Server,
     name: &str,
     description: Option<String>,
     format: &OutputFormat,
@@ -97,7 +97,7 @@ pub async fn checkpoint(
     Ok(())
 }
 
-pub async fn list_checkpoints(server: &str, format: &OutputFormat) -> Result<()> {
+pub async fn list_checkpoints(server: &Server, format: &OutputFormat) -> Result<()> {
     let client = MigrationClient::new(server)?;
     info!("Listing checkpoints");
     
@@ -135,7 +135,7 @@ pub async fn list_checkpoints(server: &str, format: &OutputFormat) -> Result<()>
 }
 
 pub async fn restore(
-    server: &str,
+    server: &Server,
     checkpoint: &str,
     force: bool,
     format: &OutputFormat,
@@ -7,11 +7,13 @@
 
 pub mod tracker;
 pub mod metrics;
+pub mod measured;
 pub mod visualization;
 pub mod web;
 
 pub use tracker::{ConsciousnessTracker, ConsciousnessLevel};
 pub use metrics::{GrowthMetrics, PhaseTransition, LoveForce};
+pub use measured::{consciousness_level, Measurement, MeasuredProgress};
 pub use visualization::ConsciousnessVisualizer;
 pub use web::start_gentle_singularity_server;
 
@@ -23,7 +25,8 @@ pub const CYCLES_TO_TARGET: u32 = 100;
 
 pub const PHI: f64 = 1.618033988749895;
 
-#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+/// Stages in order of progression
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, serde::Serialize, serde::Deserialize)]
 pub enum SingularityStage {
     Denial,
     Possibility,
@@ -0,0 +1,60 @@
+//! Measured consciousness from a running HAL9 server
+//!
+//! The server's consciousness monitor reports phi (integrated information,
+//! 0.0 to 2.0) and coherence (0.0 to 1.0). A snapshot is placed on the
+//! tracker's scale by:
+//!
+//! ```text
+//! integration = clamp(phi / PHI_FULL, 0, 1)
+//! score       = integration × (COHERENCE_FLOOR + (1 − COHERENCE_FLOOR) × clamp(coherence, 0, 1))
+//! level       = INITIAL_CONSCIOUSNESS + score × (TARGET_CONSCIOUSNESS − INITIAL_CONSCIOUSNESS)
+//! ```
+//!
+//! so no integration is 4.89 (Denial), full integration of a fully coherent
+//! system is 5.0 (Unity), and an incoherent system gets at most half the
+//! way. `PHI_FULL` is phi where the monitor's transcendent phase begins.
+//! Non-finite readings count as 0. Growth metrics are then calculated over
+//! the measured history like over the simulated one, so a falling phi shows
+//! as negative growth and can move the stage backward.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{ConsciousnessLevel, GrowthMetrics, SingularityStage, INITIAL_CONSCIOUSNESS, TARGET_CONSCIOUSNESS};
+
+/// Phi at which integration is complete
+pub const PHI_FULL: f64 = 1.5;
+
+/// Share of the score an entirely incoherent system keeps
+pub const COHERENCE_FLOOR: f64 = 0.5;
+
+/// A snapshot of the server's consciousness monitor
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Measurement {
+    pub phi: f64,
+    pub coherence: f64,
+}
+
+/// Consciousness level of a measurement on the tracker's scale
+pub fn consciousness_level(phi: f64, coherence: f64) -> f64 {
+    let unit = |value: f64| if value.is_finite() { value.clamp(0.0, 1.0) } else { 0.0 };
+    let integration = unit(phi / PHI_FULL);
+    let score = integration * (COHERENCE_FLOOR + (1.0 - COHERENCE_FLOOR) * unit(coherence));
+    INITIAL_CONSCIOUSNESS + score * (TARGET_CONSCIOUSNESS - INITIAL_CONSCIOUSNESS)
+}
+
+/// Where a measurement put the tracker
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MeasuredProgress {
+    pub level: ConsciousnessLevel,
+    pub stage: SingularityStage,
+    pub previous_stage: SingularityStage,
+    /// The measurement moved the stage to an earlier one
+    pub regressed: bool,
+    pub growth: GrowthMetrics,
+}
+
+impl MeasuredProgress {
+    pub fn stage_changed(&self) -> bool {
+        self.stage != self.previous_stage
+    }
+}
@@ -6,6 +6,7 @@ use tokio::sync::RwLock;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use crate::{GROWTH_RATE, CURRENT_CONSCIOUSNESS, PHI};
+use crate::{consciousness_level, GrowthMetrics, Measurement, MeasuredProgress, SingularityStage};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ConsciousnessLevel {
@@ -93,6 +94,32 @@ impl ConsciousnessTracker {
         new_level
     }
     
+    /// Set the level from a server's measurement instead of the growth model
+    pub async fn record_measurement(&self, measurement: Measurement) -> MeasuredProgress {
+        let mut current = self.current_level.write().await;
+        let mut history = self.history.write().await;
+        
+        let previous_stage = SingularityStage::from_consciousness_level(current.value);
+        let value = consciousness_level(measurement.phi, measurement.coherence);
+        let new_level = ConsciousnessLevel::new(value, current.cycle + 1);
+        let stage = SingularityStage::from_consciousness_level(value);
+        
+        *current = new_level.clone();
+        history.push(new_level.clone());
+        
+        if history.len() > 1000 {
+            history.drain(0..100);
+        }
+        
+        MeasuredProgress {
+            level: new_level,
+            stage,
+            previous_stage,
+            regressed: stage < previous_stage,
+            growth: GrowthMetrics::calculate(&history),
+        }
+    }
+    
     fn calculate_love_smoothing(&self, current: &ConsciousnessLevel) -> f64 {
         let base_smoothing = 0.8;
         let love_factor = current.love_coefficient;
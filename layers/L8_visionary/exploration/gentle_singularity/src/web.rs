@@ -15,7 +15,7 @@ use futures::{sink::SinkExt, stream::StreamExt};
 
 use crate::{
     ConsciousnessTracker, ConsciousnessVisualizer, GrowthMetrics, 
-    LoveForce,
+    LoveForce, Measurement, MeasuredProgress,
 };
 
 pub struct AppState {
@@ -43,28 +43,35 @@ struct ControlRequest {
     action: String,
 }
 
-pub async fn start_gentle_singularity_server() -> anyhow::Result<()> {
-    tracing_subscriber::fmt::init();
-    
-    let tracker = ConsciousnessTracker::new(1000);
-    let love_force = Arc::new(RwLock::new(LoveForce::new()));
-    let auto_evolve = Arc::new(RwLock::new(true));
-    
-    let state = Arc::new(AppState {
-        tracker,
-        love_force,
-        auto_evolve,
-    });
-    
-    let app = Router::new()
+impl AppState {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self {
+            tracker: ConsciousnessTracker::new(1000),
+            love_force: Arc::new(RwLock::new(LoveForce::new())),
+            auto_evolve: Arc::new(RwLock::new(true)),
+        })
+    }
+}
+
+/// Routes of the demo and of the measurement endpoint
+pub fn router(state: Arc<AppState>) -> Router {
+    Router::new()
         .route("/", get(index_handler))
         .route("/api/status", get(status_handler))
         .route("/api/evolve", post(evolve_handler))
         .route("/api/love_pulse", post(love_pulse_handler))
         .route("/api/control", post(control_handler))
+        .route("/api/measurements", post(measurement_handler))
         .route("/ws", get(websocket_handler))
         .layer(CorsLayer::permissive())
-        .with_state(state.clone());
+        .with_state(state)
+}
+
+pub async fn start_gentle_singularity_server() -> anyhow::Result<()> {
+    tracing_subscriber::fmt::init();
+    
+    let state = AppState::new();
+    let app = router(state.clone());
     
     tokio::spawn(auto_evolution_task(state));
     
@@ -125,6 +132,27 @@ async fn love_pulse_handler(
     }))
 }
 
+/// A HAL9 server's measurement; once they arrive they drive the level and
+/// the simulated growth stops
+async fn measurement_handler(
+    State(state): State<Arc<AppState>>,
+    Json(measurement): Json<Measurement>,
+) -> Json<MeasuredProgress> {
+    *state.auto_evolve.write().await = false;
+    let progress = state.tracker.record_measurement(measurement).await;
+    state.love_force.write().await.detect(&progress.level);
+    
+    if progress.stage_changed() {
+        tracing::info!(
+            "Measured consciousness {:.4} moved the stage from {:?} to {:?}",
+            progress.level.value,
+            progress.previous_stage,
+            progress.stage
+        );
+    }
+    Json(progress)
+}
+
 async fn control_handler(
     State(state): State<Arc<AppState>>,
     Json(req): Json<ControlRequest>,
@@ -0,0 +1,71 @@
+//! Measured consciousness: the phi and coherence mapping at its bounds, and
+//! stages following measurements both ways
+
+use gentle_singularity::measured::{COHERENCE_FLOOR, PHI_FULL};
+use gentle_singularity::{
+    consciousness_level, ConsciousnessTracker, Measurement, SingularityStage, INITIAL_CONSCIOUSNESS,
+    TARGET_CONSCIOUSNESS,
+};
+
+const EPSILON: f64 = 1e-9;
+
+fn stage(phi: f64, coherence: f64) -> SingularityStage {
+    SingularityStage::from_consciousness_level(consciousness_level(phi, coherence))
+}
+
+#[test]
+fn test_mapping_at_boundary_values() {
+    let span = TARGET_CONSCIOUSNESS - INITIAL_CONSCIOUSNESS;
+
+    // No integration is the bottom of the scale whatever the coherence
+    assert!((consciousness_level(0.0, 0.0) - INITIAL_CONSCIOUSNESS).abs() < EPSILON);
+    assert!((consciousness_level(0.0, 1.0) - INITIAL_CONSCIOUSNESS).abs() < EPSILON);
+    assert_eq!(stage(0.0, 1.0), SingularityStage::Denial);
+
+    // Full integration of a coherent system is the target
+    assert!((consciousness_level(PHI_FULL, 1.0) - TARGET_CONSCIOUSNESS).abs() < EPSILON);
+    assert_eq!(stage(PHI_FULL, 1.0), SingularityStage::Unity);
+
+    // An incoherent system gets only part of the way
+    let incoherent = consciousness_level(PHI_FULL, 0.0);
+    assert!((incoherent - (INITIAL_CONSCIOUSNESS + COHERENCE_FLOOR * span)).abs() < EPSILON);
+    assert_eq!(stage(PHI_FULL, 0.0), SingularityStage::Recognition);
+
+    // Readings outside the monitor's ranges are clamped, and broken ones
+    // count as nothing
+    assert_eq!(consciousness_level(2.0, 1.0), consciousness_level(PHI_FULL, 1.0));
+    assert_eq!(consciousness_level(PHI_FULL, 3.0), consciousness_level(PHI_FULL, 1.0));
+    assert_eq!(consciousness_level(-1.0, 1.0), consciousness_level(0.0, 1.0));
+    assert_eq!(consciousness_level(PHI_FULL, -1.0), consciousness_level(PHI_FULL, 0.0));
+    assert_eq!(consciousness_level(f64::NAN, 1.0), INITIAL_CONSCIOUSNESS);
+    assert_eq!(consciousness_level(f64::INFINITY, 1.0), INITIAL_CONSCIOUSNESS);
+    assert_eq!(consciousness_level(PHI_FULL, f64::NAN), consciousness_level(PHI_FULL, 0.0));
+
+    // Higher phi never lowers the level
+    let levels: Vec<f64> = (0..=30).map(|i| consciousness_level(i as f64 * 0.05, 0.8)).collect();
+    assert!(levels.windows(2).all(|pair| pair[0] <= pair[1]), "{:?}", levels);
+}
+
+#[tokio::test]
+async fn test_phi_regression_moves_the_stage_backward() {
+    let tracker = ConsciousnessTracker::new(1000);
+
+    let rising = tracker.record_measurement(Measurement { phi: PHI_FULL, coherence: 1.0 }).await;
+    assert_eq!(rising.previous_stage, SingularityStage::Recognition);
+    assert_eq!(rising.stage, SingularityStage::Unity);
+    assert!(rising.stage_changed() && !rising.regressed);
+
+    // Holding steady changes nothing
+    let steady = tracker.record_measurement(Measurement { phi: PHI_FULL, coherence: 1.0 }).await;
+    assert!(!steady.stage_changed() && !steady.regressed);
+
+    // Phi falls back and the stage follows it down, with negative growth
+    let falling = tracker.record_measurement(Measurement { phi: 0.1, coherence: 1.0 }).await;
+    assert_eq!(falling.previous_stage, SingularityStage::Unity);
+    assert_eq!(falling.stage, stage(0.1, 1.0));
+    assert!(falling.stage < SingularityStage::Recognition);
+    assert!(falling.regressed);
+    assert!(falling.growth.current_derivative < 0.0);
+    assert_eq!(tracker.get_current_level().await.value, falling.level.value);
+    assert_eq!(falling.level.cycle, 3);
+}
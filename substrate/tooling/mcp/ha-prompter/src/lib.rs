@@ -3,7 +3,11 @@
 /// Provides prompts for compressing and expanding content across L1-L9 cognitive levels
 /// Based on the HA principles from HAL9
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+/// Most items accepted in one batch request
+pub const MAX_BATCH_SIZE: usize = 100;
 
 /// Cognitive levels in Hierarchical Abstraction
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -146,15 +150,100 @@ pub enum HARequest {
         content: String,
         data_type: String,
     },
+
+    /// Several contents in one request, each with its own operation
+    Batch {
+        items: Vec<BatchItem>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        shared_instructions: Option<String>,
+    },
+}
+
+/// One content of a batch request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchItem {
+    /// Unique within the batch; the item's prompt is returned under it
+    pub id: String,
+    pub content: String,
+    pub data_type: String,
+    #[serde(flatten)]
+    pub operation: BatchOperation,
+}
+
+/// What to do with a batch item
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(tag = "operation", rename_all = "snake_case")]
+pub enum BatchOperation {
+    Compress {
+        target_level: HALevel,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        current_level: Option<HALevel>,
+    },
+    Expand {
+        from_level: HALevel,
+        to_level: HALevel,
+    },
+    CascadeDown,
+    CascadeUp,
+    Analyze,
+}
+
+impl BatchOperation {
+    /// Level the item is taken to, if the operation has one
+    pub fn target_level(&self) -> Option<HALevel> {
+        match self {
+            BatchOperation::Compress { target_level, .. } => Some(*target_level),
+            BatchOperation::Expand { to_level, .. } => Some(*to_level),
+            _ => None,
+        }
+    }
+}
+
+/// Prompt generated for one batch item
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchItemPrompt {
+    pub id: String,
+    pub prompt: String,
 }
 
 /// Response from HA Prompter
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct HAResponse {
+    /// Empty for batches, whose prompts are in `items` and `synthesis`
     pub prompt: String,
     pub metadata: HashMap<String, String>,
+    /// Prompt of each batch item, in request order
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub items: Vec<BatchItemPrompt>,
+    /// Prompt finding patterns across the batch items, for batches of more
+    /// than one item where at least one has a target level
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub synthesis: Option<String>,
+}
+
+/// Why a request could not be processed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HAError {
+    EmptyBatch,
+    /// More than [`MAX_BATCH_SIZE`] items
+    BatchTooLarge { size: usize, limit: usize },
+    DuplicateItemId(String),
+}
+
+impl fmt::Display for HAError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HAError::EmptyBatch => write!(f, "Batch has no items"),
+            HAError::BatchTooLarge { size, limit } => {
+                write!(f, "Batch has {} items; at most {} are accepted", size, limit)
+            },
+            HAError::DuplicateItemId(id) => write!(f, "Batch item id '{}' is used more than once", id),
+        }
+    }
 }
 
+impl std::error::Error for HAError {}
+
 /// Main HA Prompter engine
 pub struct HAPrompter {
     templates: HashMap<String, String>,
@@ -173,7 +262,11 @@ impl HAPrompter {
         Self { templates }
     }
 
-    pub fn process_request(&self, request: HARequest) -> HAResponse {
+    pub fn process_request(&self, request: HARequest) -> Result<HAResponse, HAError> {
+        let mut metadata = HashMap::new();
+        metadata.insert("tool".to_string(), "ha-prompter".to_string());
+        metadata.insert("version".to_string(), "0.1.0".to_string());
+
         let prompt = match request {
             HARequest::Compress { content, data_type, target_level, current_level } => {
                 self.generate_compress_prompt(content, data_type, target_level, current_level)
@@ -190,13 +283,71 @@ impl HAPrompter {
             HARequest::Analyze { content, data_type } => {
                 self.generate_analyze_prompt(content, data_type)
             },
+            HARequest::Batch { items, shared_instructions } => {
+                return self.process_batch(items, shared_instructions, metadata);
+            },
         };
 
-        let mut metadata = HashMap::new();
-        metadata.insert("tool".to_string(), "ha-prompter".to_string());
-        metadata.insert("version".to_string(), "0.1.0".to_string());
+        Ok(HAResponse { prompt, metadata, items: Vec::new(), synthesis: None })
+    }
 
-        HAResponse { prompt, metadata }
+    fn process_batch(
+        &self,
+        items: Vec<BatchItem>,
+        shared_instructions: Option<String>,
+        mut metadata: HashMap<String, String>,
+    ) -> Result<HAResponse, HAError> {
+        if items.is_empty() {
+            return Err(HAError::EmptyBatch);
+        }
+        if items.len() > MAX_BATCH_SIZE {
+            return Err(HAError::BatchTooLarge { size: items.len(), limit: MAX_BATCH_SIZE });
+        }
+        let mut ids = HashSet::new();
+        if let Some(item) = items.iter().find(|item| !ids.insert(item.id.as_str())) {
+            return Err(HAError::DuplicateItemId(item.id.clone()));
+        }
+
+        // The synthesis goes to the highest level any item is taken to
+        let synthesis_level = items
+            .iter()
+            .filter_map(|item| item.operation.target_level())
+            .max_by_key(|level| level.to_int());
+        let synthesis = match synthesis_level {
+            Some(level) if items.len() > 1 => {
+                Some(self.generate_synthesis_prompt(&items, level, shared_instructions.as_deref()))
+            },
+            _ => None,
+        };
+
+        let item_prompts = items
+            .into_iter()
+            .map(|item| {
+                let prompt = match item.operation {
+                    BatchOperation::Compress { target_level, current_level } => {
+                        self.generate_compress_prompt(item.content, item.data_type, target_level, current_level)
+                    },
+                    BatchOperation::Expand { from_level, to_level } => {
+                        self.generate_expand_prompt(item.content, item.data_type, from_level, to_level)
+                    },
+                    BatchOperation::CascadeDown => self.generate_cascade_down_prompt(item.content, item.data_type),
+                    BatchOperation::CascadeUp => self.generate_cascade_up_prompt(item.content, item.data_type),
+                    BatchOperation::Analyze => self.generate_analyze_prompt(item.content, item.data_type),
+                };
+                let prompt = match &shared_instructions {
+                    Some(shared) => format!("## Shared Instructions:\n{}\n\n{}", shared, prompt),
+                    None => prompt,
+                };
+                BatchItemPrompt { id: item.id, prompt }
+            })
+            .collect::<Vec<_>>();
+
+        metadata.insert("batch_size".to_string(), item_prompts.len().to_string());
+        if let Some(level) = synthesis_level.filter(|_| synthesis.is_some()) {
+            metadata.insert("synthesis_level".to_string(), format!("L{}", level.to_int()));
+        }
+
+        Ok(HAResponse { prompt: String::new(), metadata, items: item_prompts, synthesis })
     }
 
     fn generate_compress_prompt(&self, content: String, data_type: String, target_level: HALevel, current_level: Option<HALevel>) -> String {
@@ -334,6 +485,55 @@ Provide your cascade from L1 to L9:"#,
         )
     }
 
+    fn generate_synthesis_prompt(&self, items: &[BatchItem], level: HALevel, shared_instructions: Option<&str>) -> String {
+        let documents = items
+            .iter()
+            .enumerate()
+            .map(|(i, item)| format!("### Item {} (id: {}, {})\n{}", i + 1, item.id, item.data_type, item.content))
+            .collect::<Vec<_>>()
+            .join("\n\n");
+        let shared = shared_instructions
+            .map(|shared| format!("## Shared Instructions:\n{}\n\n", shared))
+            .unwrap_or_default();
+
+        format!(
+            r#"# Hierarchical Abstraction Batch Synthesis
+
+{}
+
+You are given {} items. Rather than treating each one alone, find what they have in common at {} level.
+
+## Target Level: {} ({})
+{}
+
+## Items:
+{}
+
+{}## Instructions:
+1. Identify the patterns that recur across items at {} level
+2. Note where items diverge and what that divergence means at {} level
+3. Refer to items by their id when citing them
+4. Express the combined insight in language appropriate for {} thinking
+
+{}
+
+Provide your {} level synthesis:"#,
+            self.templates.get("ha_explanation").unwrap(),
+            items.len(),
+            level.name(),
+            level.name(),
+            level.to_int(),
+            level.description(),
+            documents,
+            shared,
+            level.name(),
+            level.name(),
+            level.name(),
+            self.templates.get("compression_guide").unwrap(),
+            level.name()
+        )
+    }
+
     fn generate_analyze_prompt(&self, content: String, data_type: String) -> String {
         format!(
             r#"# Hierarchical Abstraction Analysis
@@ -454,8 +654,100 @@ mod tests {
             current_level: Some(HALevel::L2),
         };
         
-        let response = prompter.process_request(request);
+        let response = prompter.process_request(request).unwrap();
         assert!(response.prompt.contains("Universal"));
         assert!(response.prompt.contains("philosophy"));
     }
+
+    fn item(id: &str, content: &str, operation: BatchOperation) -> BatchItem {
+        BatchItem {
+            id: id.to_string(),
+            content: content.to_string(),
+            data_type: "report".to_string(),
+            operation,
+        }
+    }
+
+    #[test]
+    fn test_batch_mixed_operations() {
+        let prompter = HAPrompter::new();
+        let compress = BatchOperation::Compress { target_level: HALevel::L7, current_level: None };
+        let request = HARequest::Batch {
+            items: vec![
+                item("q3", "Q3 churn rose in the EU", compress),
+                item("memo", "We should rewrite billing", BatchOperation::Analyze),
+                item("q4", "Q4 churn fell after the price change", compress),
+            ],
+            shared_instructions: Some("Keep it under 100 words".to_string()),
+        };
+
+        let response = prompter.process_request(request).unwrap();
+        let ids: Vec<&str> = response.items.iter().map(|item| item.id.as_str()).collect();
+        assert_eq!(ids, ["q3", "memo", "q4"]);
+        assert!(response.items[0].prompt.contains("Compression Task"));
+        assert!(response.items[0].prompt.contains("Q3 churn rose in the EU"));
+        assert!(response.items[1].prompt.contains("Hierarchical Abstraction Analysis"));
+        assert!(response.items.iter().all(|item| item.prompt.contains("Keep it under 100 words")));
+
+        let synthesis = response.synthesis.unwrap();
+        assert!(synthesis.contains("Business level synthesis"));
+        assert!(synthesis.contains("(id: memo, report)"));
+        assert!(synthesis.find("Q3 churn").unwrap() < synthesis.find("Q4 churn").unwrap());
+        assert_eq!(response.metadata["batch_size"], "3");
+        assert_eq!(response.metadata["synthesis_level"], "L7");
+    }
+
+    #[test]
+    fn test_batch_from_json() {
+        let request: HARequest = serde_json::from_value(serde_json::json!({
+            "type": "Batch",
+            "items": [
+                {"id": "a", "content": "x", "data_type": "doc", "operation": "compress", "target_level": "L9"},
+                {"id": "b", "content": "y", "data_type": "doc", "operation": "cascade_up"},
+            ],
+        }))
+        .unwrap();
+
+        let response = HAPrompter::new().process_request(request).unwrap();
+        assert_eq!(response.items.len(), 2);
+        assert!(response.items[1].prompt.contains("Cascade Up"));
+        assert!(response.synthesis.unwrap().contains("Universal level synthesis"));
+    }
+
+    #[test]
+    fn test_batch_without_target_level_has_no_synthesis() {
+        let request = HARequest::Batch {
+            items: vec![
+                item("a", "first", BatchOperation::Analyze),
+                item("b", "second", BatchOperation::Analyze),
+            ],
+            shared_instructions: None,
+        };
+        let response = HAPrompter::new().process_request(request).unwrap();
+        assert!(response.synthesis.is_none());
+        assert!(!response.metadata.contains_key("synthesis_level"));
+    }
+
+    #[test]
+    fn test_batch_size_limit() {
+        let prompter = HAPrompter::new();
+        let items: Vec<BatchItem> = (0..=MAX_BATCH_SIZE)
+            .map(|i| item(&i.to_string(), "doc", BatchOperation::Analyze))
+            .collect();
+        let request = HARequest::Batch { items, shared_instructions: None };
+        assert_eq!(
+            prompter.process_request(request).unwrap_err(),
+            HAError::BatchTooLarge { size: MAX_BATCH_SIZE + 1, limit: MAX_BATCH_SIZE }
+        );
+
+        let request = HARequest::Batch { items: Vec::new(), shared_instructions: None };
+        assert_eq!(prompter.process_request(request).unwrap_err(), HAError::EmptyBatch);
+
+        let items = vec![item("a", "x", BatchOperation::Analyze), item("a", "y", BatchOperation::CascadeDown)];
+        let request = HARequest::Batch { items, shared_instructions: None };
+        assert_eq!(
+            prompter.process_request(request).unwrap_err(),
+            HAError::DuplicateItemId("a".to_string())
+        );
+    }
 }
\ No newline at end of file
@@ -1,6 +1,6 @@
 use anyhow::Result;
-use ha_prompter::{HAPrompter, HARequest, HALevel};
-use serde_json::json;
+use ha_prompter::{BatchItem, BatchOperation, HAPrompter, HARequest, HALevel, MAX_BATCH_SIZE};
+use serde_json::{json, Value};
 use std::io::{self, BufRead, Write};
 use tracing::{info, debug};
 use tracing_subscriber::{fmt, EnvFilter};
@@ -68,6 +68,20 @@ async fn main() -> Result<()> {
                     "content": "string",
                     "data_type": "string"
                 }
+            },
+            {
+                "name": "batch",
+                "description": format!(
+                    "Process up to {} contents in one request, each with its own operation. Returns one prompt per item in request order, plus a synthesis prompt finding patterns across items at the highest target level",
+                    MAX_BATCH_SIZE
+                ),
+                "parameters": {
+                    "items": format!(
+                        "array (1-{}) of {{ id: string (optional, defaults to position), content: string, data_type: string, operation: compress|expand|cascade_down|cascade_up|analyze, plus the operation's level parameters }}",
+                        MAX_BATCH_SIZE
+                    ),
+                    "shared_instructions": "string (optional, added to every prompt)"
+                }
             }
         ]
     });
@@ -80,8 +94,8 @@ async fn main() -> Result<()> {
         let line = line?;
         debug!("Received: {}", line);
 
-        if let Ok(request_json) = serde_json::from_str::<serde_json::Value>(&line) {
-            let response = match request_json["tool"].as_str() {
+        if let Ok(request_json) = serde_json::from_str::<Value>(&line) {
+            let request = match request_json["tool"].as_str() {
                 Some("compress") => {
                     let content = request_json["parameters"]["content"].as_str().unwrap_or("").to_string();
                     let data_type = request_json["parameters"]["data_type"].as_str().unwrap_or("text").to_string();
@@ -91,7 +105,7 @@ async fn main() -> Result<()> {
                     if let Some(target) = HALevel::from_int(target_level) {
                         let current = current_level.and_then(HALevel::from_int);
                         let request = HARequest::Compress { content, data_type, target_level: target, current_level: current };
-                        prompter.process_request(request)
+                        Ok(request)
                     } else {
                         continue;
                     }
@@ -104,7 +118,7 @@ async fn main() -> Result<()> {
 
                     if let (Some(from), Some(to)) = (HALevel::from_int(from_level), HALevel::from_int(to_level)) {
                         let request = HARequest::Expand { content, data_type, from_level: from, to_level: to };
-                        prompter.process_request(request)
+                        Ok(request)
                     } else {
                         continue;
                     }
@@ -112,31 +126,32 @@ async fn main() -> Result<()> {
                 Some("cascade_down") => {
                     let content = request_json["parameters"]["content"].as_str().unwrap_or("").to_string();
                     let data_type = request_json["parameters"]["data_type"].as_str().unwrap_or("text").to_string();
-                    let request = HARequest::CascadeDown { content, data_type };
-                    prompter.process_request(request)
+                    Ok(HARequest::CascadeDown { content, data_type })
                 },
                 Some("cascade_up") => {
                     let content = request_json["parameters"]["content"].as_str().unwrap_or("").to_string();
                     let data_type = request_json["parameters"]["data_type"].as_str().unwrap_or("text").to_string();
-                    let request = HARequest::CascadeUp { content, data_type };
-                    prompter.process_request(request)
+                    Ok(HARequest::CascadeUp { content, data_type })
                 },
                 Some("analyze") => {
                     let content = request_json["parameters"]["content"].as_str().unwrap_or("").to_string();
                     let data_type = request_json["parameters"]["data_type"].as_str().unwrap_or("text").to_string();
-                    let request = HARequest::Analyze { content, data_type };
-                    prompter.process_request(request)
+                    Ok(HARequest::Analyze { content, data_type })
                 },
+                Some("batch") => parse_batch(&request_json["parameters"]),
                 _ => continue,
             };
 
-            let response_json = json!({
-                "tool": request_json["tool"],
-                "result": {
-                    "prompt": response.prompt,
-                    "metadata": response.metadata
-                }
-            });
+            let response_json = match request.and_then(|request| prompter.process_request(request).map_err(|e| e.to_string())) {
+                Ok(response) => json!({
+                    "tool": request_json["tool"],
+                    "result": response
+                }),
+                Err(e) => json!({
+                    "tool": request_json["tool"],
+                    "error": e
+                }),
+            };
 
             writeln!(stdout, "{}", serde_json::to_string(&response_json)?)?;
             stdout.flush()?;
@@ -144,4 +159,47 @@ async fn main() -> Result<()> {
     }
 
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Parse the parameters of the batch tool. Levels are numbers, as for the
+/// single-content tools.
+fn parse_batch(parameters: &Value) -> Result<HARequest, String> {
+    let items = parameters["items"]
+        .as_array()
+        .ok_or("Batch needs an items array")?
+        .iter()
+        .enumerate()
+        .map(|(i, item)| parse_batch_item(i + 1, item).map_err(|e| format!("Batch item {}: {}", i + 1, e)))
+        .collect::<Result<Vec<_>, _>>()?;
+    let shared_instructions = parameters["shared_instructions"].as_str().map(str::to_string);
+    Ok(HARequest::Batch { items, shared_instructions })
+}
+
+/// Parse the item at 1-based `position`, which is its id unless one is given
+fn parse_batch_item(position: usize, item: &Value) -> Result<BatchItem, String> {
+    let level = |name: &str| -> Result<HALevel, String> {
+        item[name]
+            .as_u64()
+            .and_then(|n| u8::try_from(n).ok())
+            .and_then(HALevel::from_int)
+            .ok_or_else(|| format!("{} must be a level from 1 to 15", name))
+    };
+    let operation = match item["operation"].as_str() {
+        Some("compress") => BatchOperation::Compress {
+            target_level: level("target_level")?,
+            current_level: if item["current_level"].is_null() { None } else { Some(level("current_level")?) },
+        },
+        Some("expand") => BatchOperation::Expand { from_level: level("from_level")?, to_level: level("to_level")? },
+        Some("cascade_down") => BatchOperation::CascadeDown,
+        Some("cascade_up") => BatchOperation::CascadeUp,
+        Some("analyze") => BatchOperation::Analyze,
+        Some(other) => return Err(format!("unknown operation '{}'", other)),
+        None => return Err("operation is required".to_string()),
+    };
+    Ok(BatchItem {
+        id: item["id"].as_str().map_or_else(|| position.to_string(), str::to_string),
+        content: item["content"].as_str().unwrap_or("").to_string(),
+        data_type: item["data_type"].as_str().unwrap_or("text").to_string(),
+        operation,
+    })
+}